@@ -268,6 +268,9 @@ impl AccessibilityAudit {
             A11yIssue::MissingTitle => {
                 SuggestedFix::new("Add a descriptive <title> element in <head>")
             }
+            A11yIssue::VagueLink { .. } => {
+                SuggestedFix::new("Use link text that describes where the link goes")
+            }
             _ => SuggestedFix::new("Fix the accessibility issue"),
         }
     }
@@ -0,0 +1,727 @@
+//! Browser Chrome Accessibility
+//!
+//! Accessibility tree and keyboard operability for the browser's own UI
+//! (tab strip, toolbar/URL bar, application menu).
+//!
+//! - F6 / Shift+F6 cycle between chrome regions and the page content
+//! - Arrow keys, Home and End move within the tab strip, toolbar and menus
+//! - Enter / Space activate, Escape closes the open menu
+//! - M toggles audio mute of the focused tab
+//! - Focus changes are forwarded to the platform bridge (AT-SPI2, UIA, ...)
+
+use crate::aria::{AriaRole, AriaState};
+use crate::focus::FocusIndicator;
+use crate::keyboard_nav::KeyboardShortcut;
+use crate::platform::PlatformAccessibility;
+use crate::screen_reader::AnnouncePriority;
+use crate::tree::{AccessibilityTree, NodeBounds};
+
+/// Top-level chrome region reachable with F6
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChromeRegion {
+    TabStrip,
+    Toolbar,
+    #[default]
+    Content,
+}
+
+impl ChromeRegion {
+    /// Region after this one in F6 order
+    pub fn next(self) -> Self {
+        match self {
+            Self::TabStrip => Self::Toolbar,
+            Self::Toolbar => Self::Content,
+            Self::Content => Self::TabStrip,
+        }
+    }
+
+    /// Region before this one in F6 order
+    pub fn prev(self) -> Self {
+        match self {
+            Self::TabStrip => Self::Content,
+            Self::Toolbar => Self::TabStrip,
+            Self::Content => Self::Toolbar,
+        }
+    }
+
+    /// Accessible label announced when the region receives focus
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::TabStrip => "Tabs",
+            Self::Toolbar => "Navigation toolbar",
+            Self::Content => "Page content",
+        }
+    }
+}
+
+/// Item the chrome keyboard focus is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChromeFocusTarget {
+    /// Tab at index in strip order
+    Tab(usize),
+    /// Toolbar control at index (see `ToolbarControl`)
+    Toolbar(usize),
+    /// Open menu item at index
+    MenuItem(usize),
+    /// Web content has focus
+    Content,
+}
+
+/// Tab strip entry
+#[derive(Debug, Clone)]
+pub struct ChromeTab {
+    pub tab_id: u64,
+    pub title: String,
+    pub active: bool,
+    pub audible: bool,
+    pub muted: bool,
+    /// Tab is being shared or is sharing the screen
+    pub capturing: bool,
+    pub bounds: NodeBounds,
+}
+
+/// Toolbar control kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolbarControl {
+    Back,
+    Forward,
+    Reload,
+    UrlBar,
+    Menu,
+}
+
+impl ToolbarControl {
+    fn role(&self) -> AriaRole {
+        match self {
+            Self::UrlBar => AriaRole::TextBox,
+            _ => AriaRole::Button,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Back => "Back",
+            Self::Forward => "Forward",
+            Self::Reload => "Reload",
+            Self::UrlBar => "Address and search bar",
+            Self::Menu => "Application menu",
+        }
+    }
+}
+
+/// Toolbar control entry
+#[derive(Debug, Clone)]
+pub struct ChromeToolbarItem {
+    pub control: ToolbarControl,
+    pub enabled: bool,
+    /// Current text value (URL bar)
+    pub value: Option<String>,
+    pub bounds: NodeBounds,
+}
+
+/// Application menu item
+#[derive(Debug, Clone)]
+pub struct ChromeMenuItem {
+    pub id: String,
+    pub label: String,
+    pub enabled: bool,
+    pub shortcut: Option<KeyboardShortcut>,
+    pub bounds: NodeBounds,
+}
+
+impl ChromeMenuItem {
+    pub fn new(id: &str, label: &str) -> Self {
+        Self {
+            id: id.into(),
+            label: label.into(),
+            enabled: true,
+            shortcut: None,
+            bounds: NodeBounds::default(),
+        }
+    }
+
+    pub fn with_shortcut(mut self, shortcut: KeyboardShortcut) -> Self {
+        self.shortcut = Some(shortcut);
+        self
+    }
+}
+
+/// Action requested by keyboard interaction with the chrome
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChromeUiAction {
+    /// Keyboard focus moved (redraw focus ring)
+    FocusMoved(ChromeFocusTarget),
+    /// Activate the given tab
+    SelectTab(u64),
+    /// Close the given tab
+    CloseTab(u64),
+    /// Toggle audio mute of the given tab
+    ToggleTabMute(u64),
+    /// Activate a toolbar button
+    PressToolbar(ToolbarControl),
+    /// Start editing the URL bar
+    EditUrlBar,
+    /// Activate a menu item by id
+    ActivateMenuItem(String),
+    /// Menu opened or closed
+    MenuToggled(bool),
+    /// Focus returned to web content
+    FocusContent,
+}
+
+/// Chrome UI accessibility controller
+pub struct ChromeAccessibility {
+    tree: AccessibilityTree,
+    tabs: Vec<ChromeTab>,
+    toolbar: Vec<ChromeToolbarItem>,
+    menu: Vec<ChromeMenuItem>,
+    menu_open: bool,
+    region: ChromeRegion,
+    focus: ChromeFocusTarget,
+    /// Focus came from the keyboard (show focus ring)
+    focus_visible: bool,
+    indicator: FocusIndicator,
+    /// Node IDs of tabs, toolbar items, menu items (rebuilt with tree)
+    tab_nodes: Vec<u64>,
+    toolbar_nodes: Vec<u64>,
+    menu_nodes: Vec<u64>,
+    bridge: Option<Box<dyn PlatformAccessibility>>,
+}
+
+impl std::fmt::Debug for ChromeAccessibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChromeAccessibility")
+            .field("region", &self.region)
+            .field("focus", &self.focus)
+            .field("menu_open", &self.menu_open)
+            .field("tabs", &self.tabs.len())
+            .finish()
+    }
+}
+
+impl Default for ChromeAccessibility {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChromeAccessibility {
+    pub fn new() -> Self {
+        let mut chrome = Self {
+            tree: AccessibilityTree::new(),
+            tabs: Vec::new(),
+            toolbar: Vec::new(),
+            menu: Vec::new(),
+            menu_open: false,
+            region: ChromeRegion::Content,
+            focus: ChromeFocusTarget::Content,
+            focus_visible: false,
+            indicator: FocusIndicator::default(),
+            tab_nodes: Vec::new(),
+            toolbar_nodes: Vec::new(),
+            menu_nodes: Vec::new(),
+            bridge: None,
+        };
+        chrome.rebuild();
+        chrome
+    }
+
+    /// Attach a platform bridge that receives tree and focus updates
+    pub fn attach_bridge(&mut self, bridge: Box<dyn PlatformAccessibility>) {
+        self.bridge = Some(bridge);
+        if let Some(bridge) = &self.bridge {
+            bridge.update_tree(&self.tree);
+        }
+    }
+
+    /// Accessibility tree for the chrome
+    pub fn tree(&self) -> &AccessibilityTree {
+        &self.tree
+    }
+
+    pub fn region(&self) -> ChromeRegion {
+        self.region
+    }
+
+    pub fn focus(&self) -> ChromeFocusTarget {
+        self.focus
+    }
+
+    pub fn is_menu_open(&self) -> bool {
+        self.menu_open
+    }
+
+    /// Whether keyboard focus is currently inside the chrome
+    pub fn has_chrome_focus(&self) -> bool {
+        self.focus != ChromeFocusTarget::Content
+    }
+
+    /// Replace tab strip entries
+    pub fn set_tabs(&mut self, tabs: Vec<ChromeTab>) {
+        self.tabs = tabs;
+        if let ChromeFocusTarget::Tab(i) = self.focus {
+            if self.tabs.is_empty() {
+                self.focus = ChromeFocusTarget::Content;
+                self.region = ChromeRegion::Content;
+            } else if i >= self.tabs.len() {
+                self.focus = ChromeFocusTarget::Tab(self.tabs.len() - 1);
+            }
+        }
+        self.rebuild();
+    }
+
+    /// Replace toolbar controls
+    pub fn set_toolbar(&mut self, items: Vec<ChromeToolbarItem>) {
+        self.toolbar = items;
+        self.rebuild();
+    }
+
+    /// Replace application menu items
+    pub fn set_menu(&mut self, items: Vec<ChromeMenuItem>) {
+        self.menu = items;
+        if self.menu_open && self.menu.is_empty() {
+            self.menu_open = false;
+        }
+        self.rebuild();
+    }
+
+    /// Pointer interaction hides the focus ring until the next key press
+    pub fn pointer_used(&mut self) {
+        self.focus_visible = false;
+    }
+
+    /// Rebuild the accessibility tree from current chrome state
+    pub fn rebuild(&mut self) {
+        let mut tree = AccessibilityTree::new();
+        let root = tree.create_root();
+        if let Some(node) = tree.get_node_mut(root) {
+            node.role = AriaRole::Application;
+            node.set_name("fOS Browser");
+        }
+
+        // Tab strip
+        let tablist = tree.add_node(AriaRole::TabList, Some(root));
+        if let Some(node) = tree.get_node_mut(tablist) {
+            node.set_name(ChromeRegion::TabStrip.label());
+        }
+        let set_size = self.tabs.len() as u32;
+        self.tab_nodes = self.tabs.iter().enumerate().map(|(i, tab)| {
+            let id = tree.add_node(AriaRole::Tab, Some(tablist));
+            if let Some(node) = tree.get_node_mut(id) {
+                let mut name = if tab.muted {
+                    format!("{} (muted)", tab.title)
+                } else if tab.audible {
+                    format!("{} (playing audio)", tab.title)
+                } else {
+                    tab.title.clone()
+                };
+                if tab.capturing {
+                    name.push_str(" (sharing)");
+                }
+                node.set_name(name);
+                node.focusable = true;
+                node.bounds = tab.bounds.clone();
+                node.aria.states.insert("selected".into(), AriaState::Selected(tab.active));
+                node.aria.states.insert("posinset".into(), AriaState::PosInSet(i as u32 + 1));
+                node.aria.states.insert("setsize".into(), AriaState::SetSize(set_size));
+            }
+            id
+        }).collect();
+
+        // Toolbar
+        let toolbar = tree.add_node(AriaRole::Toolbar, Some(root));
+        if let Some(node) = tree.get_node_mut(toolbar) {
+            node.set_name(ChromeRegion::Toolbar.label());
+        }
+        let menu_open = self.menu_open;
+        self.toolbar_nodes = self.toolbar.iter().map(|item| {
+            let id = tree.add_node(item.control.role(), Some(toolbar));
+            if let Some(node) = tree.get_node_mut(id) {
+                node.set_name(item.control.label());
+                node.value = item.value.clone();
+                node.focusable = true;
+                node.bounds = item.bounds.clone();
+                node.aria.states.insert("disabled".into(), AriaState::Disabled(!item.enabled));
+                if item.control == ToolbarControl::Menu {
+                    node.aria.states.insert("expanded".into(), AriaState::Expanded(menu_open));
+                }
+            }
+            id
+        }).collect();
+
+        // Application menu (only exposed while open)
+        self.menu_nodes.clear();
+        if self.menu_open {
+            let menu = tree.add_node(AriaRole::Menu, Some(root));
+            if let Some(node) = tree.get_node_mut(menu) {
+                node.set_name(ToolbarControl::Menu.label());
+            }
+            self.menu_nodes = self.menu.iter().map(|item| {
+                let id = tree.add_node(AriaRole::MenuItem, Some(menu));
+                if let Some(node) = tree.get_node_mut(id) {
+                    node.set_name(item.label.clone());
+                    node.focusable = item.enabled;
+                    node.bounds = item.bounds.clone();
+                    node.aria.states.insert("disabled".into(), AriaState::Disabled(!item.enabled));
+                    if let Some(shortcut) = &item.shortcut {
+                        node.description = shortcut.display();
+                    }
+                }
+                id
+            }).collect();
+        }
+
+        // Page content placeholder
+        let content = tree.add_node(AriaRole::Document, Some(root));
+        if let Some(node) = tree.get_node_mut(content) {
+            node.set_name(ChromeRegion::Content.label());
+            node.focusable = true;
+        }
+
+        // Reflect current focus
+        if let Some(id) = Self::node_for(&self.tab_nodes, &self.toolbar_nodes, &self.menu_nodes, self.focus) {
+            if let Some(node) = tree.get_node_mut(id) {
+                node.focused = true;
+            }
+        }
+
+        self.tree = tree;
+        if let Some(bridge) = &self.bridge {
+            bridge.update_tree(&self.tree);
+        }
+    }
+
+    fn node_for(tabs: &[u64], toolbar: &[u64], menu: &[u64], target: ChromeFocusTarget) -> Option<u64> {
+        match target {
+            ChromeFocusTarget::Tab(i) => tabs.get(i).copied(),
+            ChromeFocusTarget::Toolbar(i) => toolbar.get(i).copied(),
+            ChromeFocusTarget::MenuItem(i) => menu.get(i).copied(),
+            ChromeFocusTarget::Content => None,
+        }
+    }
+
+    /// Accessibility node ID of the focused chrome item
+    pub fn focused_node(&self) -> Option<u64> {
+        Self::node_for(&self.tab_nodes, &self.toolbar_nodes, &self.menu_nodes, self.focus)
+    }
+
+    /// Focus ring to draw for the focused chrome item, if visible
+    pub fn focus_ring(&self) -> Option<(&NodeBounds, &FocusIndicator)> {
+        if !self.focus_visible {
+            return None;
+        }
+        let node = self.tree.get_node(self.focused_node()?)?;
+        Some((&node.bounds, &self.indicator))
+    }
+
+    /// Handle a key press. Returns `None` if the key is not consumed by the chrome.
+    pub fn handle_key(&mut self, key: &KeyboardShortcut) -> Option<ChromeUiAction> {
+        // Region cycling works from anywhere
+        if key.key == "F6" && !key.ctrl && !key.alt {
+            let region = if key.shift { self.region.prev() } else { self.region.next() };
+            return Some(self.enter_region(region));
+        }
+
+        // F10 / Alt+F open the application menu from anywhere
+        if (key.key == "F10" && !key.shift) || (key.alt && key.key.eq_ignore_ascii_case("f")) {
+            return self.open_menu();
+        }
+
+        if self.menu_open {
+            return self.handle_menu_key(key);
+        }
+
+        match self.focus {
+            ChromeFocusTarget::Tab(i) => self.handle_tab_key(i, key),
+            ChromeFocusTarget::Toolbar(i) => self.handle_toolbar_key(i, key),
+            ChromeFocusTarget::MenuItem(_) | ChromeFocusTarget::Content => None,
+        }
+    }
+
+    fn enter_region(&mut self, region: ChromeRegion) -> ChromeUiAction {
+        self.close_menu_silently();
+
+        let target = match region {
+            ChromeRegion::TabStrip => {
+                // Land on the active tab
+                let active = self.tabs.iter().position(|t| t.active).unwrap_or(0);
+                if self.tabs.is_empty() { None } else { Some(ChromeFocusTarget::Tab(active)) }
+            }
+            ChromeRegion::Toolbar => {
+                let url_bar = self.toolbar.iter()
+                    .position(|t| t.control == ToolbarControl::UrlBar)
+                    .or_else(|| self.first_enabled_toolbar());
+                url_bar.map(ChromeFocusTarget::Toolbar)
+            }
+            ChromeRegion::Content => Some(ChromeFocusTarget::Content),
+        };
+
+        match target {
+            Some(target) => {
+                self.region = region;
+                self.set_focus(target);
+                if let Some(bridge) = &self.bridge {
+                    bridge.announce(region.label(), AnnouncePriority::Normal);
+                }
+                if target == ChromeFocusTarget::Content {
+                    ChromeUiAction::FocusContent
+                } else {
+                    ChromeUiAction::FocusMoved(target)
+                }
+            }
+            // Empty region: skip to the next one
+            None => self.enter_region(region.next()),
+        }
+    }
+
+    fn first_enabled_toolbar(&self) -> Option<usize> {
+        self.toolbar.iter().position(|t| t.enabled)
+    }
+
+    fn set_focus(&mut self, target: ChromeFocusTarget) {
+        self.focus = target;
+        self.focus_visible = true;
+
+        let tab_nodes = &self.tab_nodes;
+        let toolbar_nodes = &self.toolbar_nodes;
+        let menu_nodes = &self.menu_nodes;
+        let focused = Self::node_for(tab_nodes, toolbar_nodes, menu_nodes, target);
+
+        let all: Vec<u64> = tab_nodes.iter().chain(toolbar_nodes).chain(menu_nodes).copied().collect();
+        for id in all {
+            if let Some(node) = self.tree.get_node_mut(id) {
+                node.focused = Some(id) == focused;
+            }
+        }
+
+        if let (Some(bridge), Some(id)) = (&self.bridge, focused) {
+            bridge.focus_changed(id);
+        }
+    }
+
+    fn handle_tab_key(&mut self, index: usize, key: &KeyboardShortcut) -> Option<ChromeUiAction> {
+        let count = self.tabs.len();
+        if count == 0 {
+            return None;
+        }
+
+        // Vertical strip: Up/Down, but Left/Right are accepted too
+        let next = match key.key.as_str() {
+            "ArrowDown" | "ArrowRight" => Some((index + 1) % count),
+            "ArrowUp" | "ArrowLeft" => Some((index + count - 1) % count),
+            "Home" => Some(0),
+            "End" => Some(count - 1),
+            _ => None,
+        };
+        if let Some(next) = next {
+            let target = ChromeFocusTarget::Tab(next);
+            self.set_focus(target);
+            return Some(ChromeUiAction::FocusMoved(target));
+        }
+
+        let tab_id = self.tabs[index].tab_id;
+        match key.key.as_str() {
+            "Enter" | " " | "Space" => Some(ChromeUiAction::SelectTab(tab_id)),
+            "Delete" => Some(ChromeUiAction::CloseTab(tab_id)),
+            "m" | "M" => Some(ChromeUiAction::ToggleTabMute(tab_id)),
+            "Escape" => Some(self.enter_region(ChromeRegion::Content)),
+            _ => None,
+        }
+    }
+
+    fn handle_toolbar_key(&mut self, index: usize, key: &KeyboardShortcut) -> Option<ChromeUiAction> {
+        let item = self.toolbar.get(index)?;
+        let control = item.control;
+        let enabled = item.enabled;
+
+        // The URL bar consumes text editing keys itself
+        if control == ToolbarControl::UrlBar {
+            return match key.key.as_str() {
+                "Enter" => Some(ChromeUiAction::EditUrlBar),
+                "Escape" => Some(self.enter_region(ChromeRegion::Content)),
+                _ => None,
+            };
+        }
+
+        match key.key.as_str() {
+            "ArrowRight" => self.move_toolbar(index, true),
+            "ArrowLeft" => self.move_toolbar(index, false),
+            "Home" => self.first_enabled_toolbar().map(|i| {
+                self.set_focus(ChromeFocusTarget::Toolbar(i));
+                ChromeUiAction::FocusMoved(ChromeFocusTarget::Toolbar(i))
+            }),
+            "End" => self.toolbar.iter().rposition(|t| t.enabled).map(|i| {
+                self.set_focus(ChromeFocusTarget::Toolbar(i));
+                ChromeUiAction::FocusMoved(ChromeFocusTarget::Toolbar(i))
+            }),
+            "Enter" | " " | "Space" | "ArrowDown" if control == ToolbarControl::Menu => self.open_menu(),
+            "Enter" | " " | "Space" if enabled => Some(ChromeUiAction::PressToolbar(control)),
+            "Escape" => Some(self.enter_region(ChromeRegion::Content)),
+            _ => None,
+        }
+    }
+
+    fn move_toolbar(&mut self, index: usize, forward: bool) -> Option<ChromeUiAction> {
+        let count = self.toolbar.len();
+        let mut i = index;
+        for _ in 0..count {
+            i = if forward { (i + 1) % count } else { (i + count - 1) % count };
+            if self.toolbar[i].enabled {
+                let target = ChromeFocusTarget::Toolbar(i);
+                self.set_focus(target);
+                return Some(ChromeUiAction::FocusMoved(target));
+            }
+        }
+        None
+    }
+
+    /// Open the application menu and focus its first enabled item
+    pub fn open_menu(&mut self) -> Option<ChromeUiAction> {
+        let first = self.menu.iter().position(|m| m.enabled)?;
+        self.menu_open = true;
+        self.region = ChromeRegion::Toolbar;
+        self.rebuild();
+        self.set_focus(ChromeFocusTarget::MenuItem(first));
+        Some(ChromeUiAction::MenuToggled(true))
+    }
+
+    /// Close the application menu, returning focus to the menu button
+    pub fn close_menu(&mut self) -> Option<ChromeUiAction> {
+        if !self.menu_open {
+            return None;
+        }
+        self.close_menu_silently();
+        let button = self.toolbar.iter().position(|t| t.control == ToolbarControl::Menu);
+        let target = button.map(ChromeFocusTarget::Toolbar).unwrap_or(ChromeFocusTarget::Content);
+        self.set_focus(target);
+        Some(ChromeUiAction::MenuToggled(false))
+    }
+
+    fn close_menu_silently(&mut self) {
+        if self.menu_open {
+            self.menu_open = false;
+            self.rebuild();
+        }
+    }
+
+    fn handle_menu_key(&mut self, key: &KeyboardShortcut) -> Option<ChromeUiAction> {
+        let current = match self.focus {
+            ChromeFocusTarget::MenuItem(i) => i,
+            _ => 0,
+        };
+        let count = self.menu.len();
+
+        let step = |from: usize, forward: bool, menu: &[ChromeMenuItem]| -> Option<usize> {
+            let mut i = from;
+            for _ in 0..count {
+                i = if forward { (i + 1) % count } else { (i + count - 1) % count };
+                if menu[i].enabled {
+                    return Some(i);
+                }
+            }
+            None
+        };
+
+        let next = match key.key.as_str() {
+            "ArrowDown" => step(current, true, &self.menu),
+            "ArrowUp" => step(current, false, &self.menu),
+            "Home" => self.menu.iter().position(|m| m.enabled),
+            "End" => self.menu.iter().rposition(|m| m.enabled),
+            "Escape" | "ArrowLeft" => return self.close_menu(),
+            "Enter" | " " | "Space" => {
+                let item = self.menu.get(current).filter(|m| m.enabled)?;
+                let id = item.id.clone();
+                self.close_menu();
+                return Some(ChromeUiAction::ActivateMenuItem(id));
+            }
+            // Menus are modal for keyboard input
+            _ => return Some(ChromeUiAction::FocusMoved(self.focus)),
+        };
+
+        next.map(|i| {
+            let target = ChromeFocusTarget::MenuItem(i);
+            self.set_focus(target);
+            ChromeUiAction::FocusMoved(target)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chrome() -> ChromeAccessibility {
+        let mut chrome = ChromeAccessibility::new();
+        chrome.set_tabs(vec![
+            ChromeTab { tab_id: 10, title: "One".into(), active: false, audible: true, muted: false, capturing: false, bounds: NodeBounds::default() },
+            ChromeTab { tab_id: 11, title: "Two".into(), active: true, audible: false, muted: false, capturing: true, bounds: NodeBounds::default() },
+        ]);
+        chrome.set_toolbar(vec![
+            ChromeToolbarItem { control: ToolbarControl::Back, enabled: false, value: None, bounds: NodeBounds::default() },
+            ChromeToolbarItem { control: ToolbarControl::UrlBar, enabled: true, value: Some("about:blank".into()), bounds: NodeBounds::default() },
+            ChromeToolbarItem { control: ToolbarControl::Menu, enabled: true, value: None, bounds: NodeBounds::default() },
+        ]);
+        chrome.set_menu(vec![
+            ChromeMenuItem::new("new-tab", "New tab").with_shortcut(KeyboardShortcut::new("T").ctrl()),
+            ChromeMenuItem { enabled: false, ..ChromeMenuItem::new("print", "Print") },
+            ChromeMenuItem::new("devtools", "Developer tools"),
+        ]);
+        chrome
+    }
+
+    #[test]
+    fn test_f6_region_cycling() {
+        let mut chrome = chrome();
+        assert_eq!(chrome.region(), ChromeRegion::Content);
+
+        chrome.handle_key(&KeyboardShortcut::new("F6"));
+        assert_eq!(chrome.region(), ChromeRegion::TabStrip);
+        // Lands on the active tab
+        assert_eq!(chrome.focus(), ChromeFocusTarget::Tab(1));
+        assert!(chrome.focus_ring().is_some());
+
+        chrome.handle_key(&KeyboardShortcut::new("F6"));
+        assert_eq!(chrome.focus(), ChromeFocusTarget::Toolbar(1));
+
+        let action = chrome.handle_key(&KeyboardShortcut::new("F6").shift());
+        assert_eq!(action, Some(ChromeUiAction::FocusMoved(ChromeFocusTarget::Tab(1))));
+    }
+
+    #[test]
+    fn test_tab_strip_keys() {
+        let mut chrome = chrome();
+        chrome.handle_key(&KeyboardShortcut::new("F6"));
+        chrome.handle_key(&KeyboardShortcut::new("ArrowDown"));
+        assert_eq!(chrome.focus(), ChromeFocusTarget::Tab(0));
+        assert_eq!(chrome.handle_key(&KeyboardShortcut::new("Enter")), Some(ChromeUiAction::SelectTab(10)));
+        assert_eq!(chrome.handle_key(&KeyboardShortcut::new("m")), Some(ChromeUiAction::ToggleTabMute(10)));
+        assert_eq!(chrome.handle_key(&KeyboardShortcut::new("Delete")), Some(ChromeUiAction::CloseTab(10)));
+    }
+
+    #[test]
+    fn test_menu_navigation_skips_disabled() {
+        let mut chrome = chrome();
+        assert_eq!(chrome.handle_key(&KeyboardShortcut::new("F10")), Some(ChromeUiAction::MenuToggled(true)));
+        assert_eq!(chrome.focus(), ChromeFocusTarget::MenuItem(0));
+
+        chrome.handle_key(&KeyboardShortcut::new("ArrowDown"));
+        assert_eq!(chrome.focus(), ChromeFocusTarget::MenuItem(2));
+
+        let action = chrome.handle_key(&KeyboardShortcut::new("Enter"));
+        assert_eq!(action, Some(ChromeUiAction::ActivateMenuItem("devtools".into())));
+        assert!(!chrome.is_menu_open());
+        assert_eq!(chrome.focus(), ChromeFocusTarget::Toolbar(2));
+    }
+
+    #[test]
+    fn test_tree_roles() {
+        let chrome = chrome();
+        let tree = chrome.tree();
+        assert!(tree.find_by_name("Tabs").is_some_and(|n| n.role == AriaRole::TabList));
+        assert!(tree.find_by_name("One (playing audio)").is_some());
+        assert!(tree.find_by_name("Two (sharing)").is_some_and(|n| {
+            n.aria.states.get("selected") == Some(&AriaState::Selected(true))
+        }));
+        assert_eq!(tree.get_focusable_nodes().len(), 6);
+    }
+}
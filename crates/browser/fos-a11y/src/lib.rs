@@ -18,6 +18,7 @@
 //! - Media preferences (reduced-data, reduced-transparency)
 //! - Auto-fix suggestions
 //! - Reading mode
+//! - Browser chrome UI tree with F6 region cycling
 
 pub mod aria;
 pub mod tree;
@@ -33,6 +34,7 @@ pub mod alternative_input;
 pub mod media_preferences;
 pub mod auto_fix;
 pub mod reading_mode;
+pub mod chrome_ui;
 
 // Core exports
 pub use aria::{AriaRole, AriaState, AriaAttributes, LiveRegionMode, LiveRelevant, DropEffect};
@@ -58,6 +60,10 @@ pub use alternative_input::{SwitchAccessManager, VoiceControlManager, ScanMode,
 pub use media_preferences::{MediaPreferences, TransparencyPreference, DataPreference};
 pub use auto_fix::{AccessibilityAudit, A11yIssue, IssueSeverity, SuggestedFix};
 pub use reading_mode::{ReadingMode, ReadingModeSettings};
+pub use chrome_ui::{
+    ChromeAccessibility, ChromeRegion, ChromeFocusTarget, ChromeTab, ChromeToolbarItem,
+    ChromeMenuItem, ToolbarControl, ChromeUiAction,
+};
 
 /// Accessibility error
 #[derive(Debug, thiserror::Error)]
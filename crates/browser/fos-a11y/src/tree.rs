@@ -109,6 +109,11 @@ impl AccessibilityTree {
         id
     }
     
+    /// Root node ID, once created
+    pub fn root_id(&self) -> Option<u64> {
+        self.root_id
+    }
+    
     /// Get node by ID
     pub fn get_node(&self, id: u64) -> Option<&AccessibilityNode> {
         self.nodes.iter().find(|n| n.id == id)
@@ -3,9 +3,12 @@
 //! Integrates fos-a11y for keyboard navigation, focus management,
 //! and accessibility tree support.
 
+use std::collections::HashMap;
+
+use fos_css::computed::{ComputedStyle, Display};
 use fos_dom::{Document, DomTree, NodeId};
 use fos_a11y::{
-    AccessibilityTree, AriaRole,
+    AccessibilityTree, AriaAttributes, AriaRole,
     FocusManager, FocusIndicator,
 };
 
@@ -21,6 +24,8 @@ pub struct AccessibilityManager {
     link_regions: Vec<FocusableRegion>,
     /// Form input regions
     input_regions: Vec<FocusableRegion>,
+    /// DOM element each accessibility node was built from
+    dom_nodes: HashMap<u64, NodeId>,
 }
 
 /// A focusable region in the page
@@ -63,19 +68,32 @@ impl AccessibilityManager {
             focus_indicator: FocusIndicator::default(),
             link_regions: Vec::new(),
             input_regions: Vec::new(),
+            dom_nodes: HashMap::new(),
         }
     }
     
     /// Build accessibility tree from DOM document
     pub fn build_from_document(&mut self, document: &Document) {
+        self.build(document, None);
+    }
+    
+    /// Build accessibility tree from a styled DOM document. `display: none`
+    /// subtrees are left out; `display: contents` elements generate no box but
+    /// keep their node and role, with their children beneath them
+    pub fn build_from_styled_document(&mut self, document: &Document, styles: &HashMap<NodeId, ComputedStyle>) {
+        self.build(document, Some(styles));
+    }
+    
+    fn build(&mut self, document: &Document, styles: Option<&HashMap<NodeId, ComputedStyle>>) {
         self.tree = AccessibilityTree::new();
         self.link_regions.clear();
         self.input_regions.clear();
+        self.dom_nodes.clear();
         
         let tree = document.tree();
         let root_id = self.tree.create_root();
         
-        self.build_tree_recursive(tree, tree.root(), root_id);
+        self.build_tree_recursive(tree, styles, tree.root(), root_id);
         
         // Build focus order from collected regions
         let focus_order: Vec<u64> = self.link_regions.iter()
@@ -90,15 +108,31 @@ impl AccessibilityManager {
     }
     
     /// Recursively build accessibility tree from DOM
-    fn build_tree_recursive(&mut self, tree: &DomTree, node_id: NodeId, parent_a11y_id: u64) {
+    fn build_tree_recursive(
+        &mut self,
+        tree: &DomTree,
+        styles: Option<&HashMap<NodeId, ComputedStyle>>,
+        node_id: NodeId,
+        parent_a11y_id: u64,
+    ) {
         if !node_id.is_valid() {
             return;
         }
         
+        // Hidden subtrees aren't exposed
+        if styles.and_then(|s| s.get(&node_id)).is_some_and(|s| s.display == Display::None) {
+            return;
+        }
+        
         if let Some(node) = tree.get(node_id) {
             if let Some(element) = node.as_element() {
                 let tag = tree.resolve(element.name.local).to_lowercase();
                 
+                // Metadata and templates are never rendered
+                if matches!(tag.as_str(), "head" | "script" | "style" | "template") {
+                    return;
+                }
+                
                 // Map HTML elements to ARIA roles
                 let role = match tag.as_str() {
                     "a" => AriaRole::Link,
@@ -122,22 +156,25 @@ impl AccessibilityManager {
                     _ => AriaRole::Generic,
                 };
                 
-                // Add to accessibility tree
-                let a11y_id = self.tree.add_node(role, Some(parent_a11y_id));
-                
                 // Extract accessible name and attributes
                 let mut name = String::new();
+                let mut title = String::new();
                 let mut href: Option<String> = None;
                 let mut input_type = "text";
+                let mut attrs: HashMap<String, String> = HashMap::new();
                 
                 for attr in element.attrs.iter() {
                     let attr_name = tree.resolve(attr.name.local);
+                    attrs.insert(attr_name.to_string(), attr.value.clone());
                     match attr_name {
-                        "aria-label" | "alt" | "title" => {
+                        "aria-label" | "alt" => {
                             if name.is_empty() {
                                 name = attr.value.clone();
                             }
                         }
+                        "title" => {
+                            title = attr.value.clone();
+                        }
                         "href" => {
                             href = Some(attr.value.clone());
                         }
@@ -148,9 +185,31 @@ impl AccessibilityManager {
                     }
                 }
                 
-                // Set accessible name
+                // An explicit role overrides the element's own
+                let aria = AriaAttributes::from_attributes(&attrs);
+                let role = aria.role.unwrap_or(role);
+                
+                // Add to accessibility tree
+                let a11y_id = self.tree.add_node(role, Some(parent_a11y_id));
+                self.dom_nodes.insert(a11y_id, node_id);
+                
+                // Links, buttons and headings are named by their text, and
+                // anything else by its title as a last resort
+                if name.is_empty() && role.supports_name_from_content() {
+                    name = text_content(tree, node_id);
+                }
+                if name.is_empty() {
+                    name = title.clone();
+                }
+                
+                // Set accessible name; a title that didn't name the
+                // element describes it
                 if let Some(a_node) = self.tree.get_node_mut(a11y_id) {
                     a_node.set_name(&name);
+                    if title != name {
+                        a_node.description = title;
+                    }
+                    a_node.aria = aria;
                     a_node.focusable = matches!(tag.as_str(), 
                         "a" | "button" | "input" | "select" | "textarea"
                     );
@@ -213,7 +272,12 @@ impl AccessibilityManager {
                 
                 // Recurse into children
                 for (child_id, _) in tree.children(node_id) {
-                    self.build_tree_recursive(tree, child_id, a11y_id);
+                    self.build_tree_recursive(tree, styles, child_id, a11y_id);
+                }
+            } else {
+                // The document and fragments add no node of their own
+                for (child_id, _) in tree.children(node_id) {
+                    self.build_tree_recursive(tree, styles, child_id, parent_a11y_id);
                 }
             }
         }
@@ -229,6 +293,11 @@ impl AccessibilityManager {
         self.focus.focus_prev()
     }
     
+    /// DOM element an accessibility node was built from
+    pub fn dom_node(&self, a11y_id: u64) -> Option<NodeId> {
+        self.dom_nodes.get(&a11y_id).copied()
+    }
+    
     /// Get currently focused element ID
     pub fn get_focused(&self) -> Option<u64> {
         self.focus.get_focused()
@@ -286,6 +355,24 @@ impl Default for AccessibilityManager {
     }
 }
 
+/// Text of an element's descendants, whitespace collapsed
+fn text_content(tree: &DomTree, node_id: NodeId) -> String {
+    fn collect(tree: &DomTree, node_id: NodeId, out: &mut String) {
+        for (child_id, child) in tree.children(node_id) {
+            match child.as_text() {
+                Some(text) => {
+                    out.push_str(text);
+                    out.push(' ');
+                }
+                None => collect(tree, child_id, out),
+            }
+        }
+    }
+    let mut text = String::new();
+    collect(tree, node_id, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 /// Accessibility statistics
 #[derive(Debug, Clone)]
 pub struct AccessibilityStats {
@@ -316,4 +403,31 @@ mod tests {
         assert_eq!(manager.focus_next(), Some(2));
         assert_eq!(manager.focus_prev(), Some(1));
     }
+    
+    #[test]
+    fn test_build_from_document() {
+        let mut document = Document::new("about:blank");
+        let body = document.body();
+        let tree = document.tree_mut();
+        let link = tree.create_element("a");
+        tree.set_attribute(link, "href", "/docs");
+        tree.set_attribute(link, "title", "Documentation");
+        let text = tree.create_text(" Read   the docs ");
+        tree.append_child(link, text);
+        tree.append_child(body, link);
+        let tab = tree.create_element("div");
+        tree.set_attribute(tab, "role", "tab");
+        tree.set_attribute(tab, "aria-label", "Settings");
+        tree.append_child(body, tab);
+        
+        let mut manager = AccessibilityManager::new();
+        manager.build_from_document(&document);
+        let links = manager.get_links();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].name, "Read the docs");
+        assert_eq!(manager.dom_node(links[0].id), Some(link));
+        let node = manager.tree.get_node(links[0].id).unwrap();
+        assert_eq!(node.description, "Documentation");
+        assert!(manager.tree.find_by_name("Settings").is_some_and(|n| n.role == AriaRole::Tab));
+    }
 }
@@ -6,6 +6,7 @@ use std::collections::HashMap;
 use fos_net::{
     WebSocket, WebSocketState, WebSocketError,
     XmlHttpRequest, ReadyState, XhrError,
+    UploadBody, UploadControl, UploadProgress,
     EventSource, EventSourceState,
 };
 use fos_security::{
@@ -181,6 +182,27 @@ impl AdvancedNetworking {
         }
     }
     
+    /// Send XHR request with a body streamed from a blob or file
+    pub fn xhr_send_upload(&mut self, id: u64, body: UploadBody) -> Result<(), XhrError> {
+        if let Some(instance) = self.xhr_requests.get_mut(&id) {
+            instance.xhr.send_upload(body)
+        } else {
+            Err(XhrError::InvalidState)
+        }
+    }
+    
+    /// Pause, resume or abort an XHR upload from another thread
+    pub fn xhr_upload_control(&self, id: u64) -> Option<UploadControl> {
+        self.xhr_requests.get(&id).map(|i| i.xhr.upload_control())
+    }
+    
+    /// Take the `xhr.upload` events to dispatch
+    pub fn xhr_take_upload_events(&mut self, id: u64) -> Vec<UploadProgress> {
+        self.xhr_requests.get_mut(&id)
+            .map(|i| i.xhr.take_upload_events())
+            .unwrap_or_default()
+    }
+    
     /// Abort XHR request
     pub fn xhr_abort(&mut self, id: u64) {
         if let Some(instance) = self.xhr_requests.get_mut(&id) {
@@ -1,25 +1,46 @@
 //! Browser Application
 //!
-//! Main browser window and event loop.
+//! Browser windows and the event loop they share. Each window has its own
+//! tab strip, surface and shown page; networking, security, memory, the
+//! session and extensions are shared. Tabs can move between windows, and
+//! closing the last window quits.
 
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::num::NonZeroU32;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::mpsc::{channel, Receiver, TryRecvError};
 use winit::application::ApplicationHandler;
-use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::event::{DeviceEvent, DeviceId, ElementState, KeyEvent, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
 use winit::keyboard::{KeyCode, PhysicalKey};
-use winit::window::{Window, WindowId};
+use winit::window::{CursorGrabMode, Window, WindowId};
+use fos_a11y::{KeyboardShortcut, ReadingMode, TextScalingSettings, ZoomLevel};
+use fos_security::ContentCategory;
+use fos_js::AppBadge;
 
 use crate::loader::Loader;
+use crate::history_store::HistoryStore;
+use crate::cert_overrides::CertificateOverrides;
+use crate::zoom::{ZoomStep, ZoomStore};
+use crate::web_apps::{manifest_link, WebApps};
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::web_apps::DesktopEntries;
+use crate::loader::{InternalAction, InternalState, CERT_ERROR_PROCEED};
+use crate::config::ConfigStore;
+use crate::telemetry::{Telemetry, TelemetryEvent};
+use crate::spellcheck::SpellChecker;
 use crate::renderer::{PageRenderer, RenderedPage};
-use crate::tab::TabManager;
-use crate::ui::Chrome;
+use crate::tab::{TabId, TabIds, TabManager, Traversal};
+use crate::ui::{Chrome, ChromeCommand};
 use crate::ui::tab_bar::TAB_BAR_WIDTH;
 use crate::ui::url_bar::URL_BAR_HEIGHT;
-use crate::network::NetworkManager;
+use crate::network::{NetworkManager, NetworkError};
+use fos_net::TlsError;
 use crate::page::Page;
+use crate::frames::{Frames, OpenRequest};
+use crate::frame_tree::FrameId;
 use crate::devtools::DevTools;
 use crate::accessibility::AccessibilityManager;
 use crate::media::MediaManager;
@@ -27,53 +48,384 @@ use crate::canvas::CanvasManager;
 use crate::advanced_net::AdvancedNetworking;
 use crate::security::SecurityManager;
 use crate::memory::MemoryIntegration;
+use fos_engine::{OomAction, ProcessArchitecture};
+use crate::pointer_lock::{CursorCapture, PointerLockManager};
+use crate::dragdrop::{DragDropManager, PageDragEvent, DOCUMENT_TARGET};
+use crate::ime::{ImeContext, ImeInput, ImeManager, ImeUpdate};
+use crate::scroll_restoration::ScrollOffset;
+use crate::session::{capture_form_fields, restore_form_fields, SessionState, SessionStore};
+use crate::service_worker::registration_api;
+#[cfg(feature = "extensions")]
+use crate::extensions::{ExtensionManager, RunAt};
+#[cfg(feature = "extensions")]
+use crate::extension_api::{ExtensionContext, ExtensionRuntime};
+#[cfg(feature = "extensions")]
+use crate::declarative_net_request::NetRequestRules;
+use fos_devtools::{ScriptLocation, TraceKind};
+use fos_devtools::performance::ScriptEventType;
 
 /// Browser application
 pub struct Browser {
     /// Initial URL to load
     initial_url: String,
+    /// Data saver preference
+    save_data: bool,
+    /// Directory the session is saved to and restored from
+    session_dir: Option<PathBuf>,
+    /// File browsing history is kept in
+    history_file: Option<PathBuf>,
+    /// File certificate error overrides are kept in
+    cert_overrides_file: Option<PathBuf>,
+    /// File page zoom per site is kept in
+    zoom_file: Option<PathBuf>,
+    /// File installed web apps are kept in
+    web_apps_file: Option<PathBuf>,
+    /// File flags changed in about:config are kept in
+    config_file: Option<PathBuf>,
 }
 
+/// Title of browser windows, after any app badge
+const WINDOW_TITLE: &str = "fOS Browser";
+
+/// How long after a click in the page its scripts may open a tab
+const POPUP_ACTIVATION: std::time::Duration = std::time::Duration::from_secs(1);
+
 impl Browser {
     /// Create a new browser instance
     pub fn new() -> Result<Self, Box<dyn Error>> {
         Ok(Self {
             initial_url: String::new(),
+            save_data: false,
+            session_dir: SessionStore::default_dir(),
+            history_file: SessionStore::default_dir().map(|dir| dir.join("history")),
+            cert_overrides_file: SessionStore::default_dir().map(|dir| dir.join("cert_overrides")),
+            zoom_file: SessionStore::default_dir().map(|dir| dir.join("zoom")),
+            web_apps_file: SessionStore::default_dir().map(|dir| dir.join("web_apps")),
+            config_file: SessionStore::default_dir().map(|dir| dir.join("config")),
         })
     }
     
+    /// Turn on the data saver: requests send `Save-Data: on`, pages see
+    /// `prefers-reduced-data: reduce` and navigator.connection.saveData
+    pub fn with_save_data(mut self, enabled: bool) -> Self {
+        self.save_data = enabled;
+        self
+    }
+    
+    /// Save the session to a directory, or not at all with `None`
+    pub fn with_session_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.session_dir = dir;
+        self
+    }
+    
+    /// Keep browsing history in a file, or only for this run with `None`
+    pub fn with_history_file(mut self, path: Option<PathBuf>) -> Self {
+        self.history_file = path;
+        self
+    }
+    
+    /// Keep certificate error overrides in a file, or only for this run
+    /// with `None`
+    pub fn with_cert_overrides_file(mut self, path: Option<PathBuf>) -> Self {
+        self.cert_overrides_file = path;
+        self
+    }
+    
+    /// Keep page zoom per site in a file, or only for this run with `None`
+    pub fn with_zoom_file(mut self, path: Option<PathBuf>) -> Self {
+        self.zoom_file = path;
+        self
+    }
+    
+    /// Keep installed web apps in a file, or only for this run with `None`
+    pub fn with_web_apps_file(mut self, path: Option<PathBuf>) -> Self {
+        self.web_apps_file = path;
+        self
+    }
+    
+    /// Keep flags changed in about:config in a file, or only for this run
+    /// with `None`
+    pub fn with_config_file(mut self, path: Option<PathBuf>) -> Self {
+        self.config_file = path;
+        self
+    }
+    
     /// Run the browser with an initial URL
+    ///
+    /// Tabs of the previous session are restored first; an initial URL
+    /// other than `about:blank` opens in a tab of its own.
     pub fn run(mut self, initial_url: String) -> Result<(), Box<dyn Error>> {
         self.initial_url = initial_url;
         
         let event_loop = EventLoop::new()?;
         event_loop.set_control_flow(ControlFlow::Wait);
         
+        let session = self.session_dir.take().map(SessionStore::new);
+        let restored = session.as_ref().and_then(SessionStore::load);
+        if let Some(ref state) = restored {
+            log::info!("Restoring session with {} tabs", state.tabs.len());
+        }
+        
         let mut app = BrowserApp::new(self.initial_url.clone());
+        app.set_save_data(self.save_data);
+        app.session = session;
+        app.restored_session = restored;
+        if let Some(path) = self.history_file.take() {
+            app.shared.history = HistoryStore::with_storage(path);
+        }
+        if let Some(path) = self.cert_overrides_file.take() {
+            app.shared.network.set_certificate_overrides(CertificateOverrides::with_storage(path));
+        }
+        if let Some(path) = self.zoom_file.take() {
+            app.shared.zoom = ZoomStore::with_storage(path);
+        }
+        if let Some(path) = self.web_apps_file.take() {
+            app.shared.web_apps = WebApps::with_storage(path);
+        }
+        if let Some(path) = self.config_file.take() {
+            app.shared.config = ConfigStore::with_storage(path);
+            app.shared.apply_config();
+        }
+        // Installed apps go in the desktop's application menu, which
+        // launches the browser with their URLs
+        #[cfg(all(unix, not(target_os = "macos")))]
+        if let (Some(dir), Ok(exe)) = (DesktopEntries::default_dir(), std::env::current_exe()) {
+            app.shared.web_apps.set_launcher(Box::new(DesktopEntries::new(dir, exe)));
+        }
         event_loop.run_app(&mut app)?;
         
         Ok(())
     }
 }
 
-/// Browser app state for event loop
-struct BrowserApp {
+/// Cursor capture for pointer lock through the window
+#[derive(Debug)]
+struct WindowCursor(Arc<Window>);
+
+impl CursorCapture for WindowCursor {
+    fn capture(&mut self) -> bool {
+        // Confining keeps cursor moves coming so they can be re-centered;
+        // platforms without it only offer a locked cursor
+        let grabbed = self.0.set_cursor_grab(CursorGrabMode::Confined)
+            .or_else(|_| self.0.set_cursor_grab(CursorGrabMode::Locked))
+            .is_ok();
+        if grabbed {
+            self.0.set_cursor_visible(false);
+        }
+        grabbed
+    }
+    
+    fn release(&mut self) {
+        let _ = self.0.set_cursor_grab(CursorGrabMode::None);
+        self.0.set_cursor_visible(true);
+    }
+    
+    fn warp(&mut self, x: f32, y: f32) {
+        let _ = self.0.set_cursor_position(winit::dpi::PhysicalPosition::new(x as f64, y as f64));
+    }
+    
+    fn supports_unadjusted_movement(&self) -> bool {
+        // Device motion events carry unaccelerated deltas
+        true
+    }
+}
+
+/// Platform input method of the window
+#[derive(Debug)]
+struct WindowIme(Arc<Window>);
+
+impl ImeContext for WindowIme {
+    fn set_allowed(&mut self, allowed: bool) {
+        self.0.set_ime_allowed(allowed);
+    }
+    
+    fn set_cursor_area(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        self.0.set_ime_cursor_area(
+            winit::dpi::PhysicalPosition::new(x, y),
+            winit::dpi::PhysicalSize::new(width, height),
+        );
+    }
+}
+
+/// Layers every window shares: networking, security, memory and extensions
+struct Shared {
+    /// Page loader
+    loader: Loader,
+    /// Network manager with HTTP cache
+    network: NetworkManager,
+    /// Developer tools
+    devtools: DevTools,
+    /// Advanced networking (WebSocket, XHR, SSE)
+    _advanced_net: AdvancedNetworking,
+    /// Security manager (CSP, sandbox, privacy)
+    security: SecurityManager,
+    /// Memory integration (pressure, hibernation)
+    memory: MemoryIntegration,
+    /// Renderer of each tab under the configured process model, held to
+    /// its memory ceiling
+    processes: ProcessArchitecture,
+    /// Data saver preference, applied to every page
+    save_data: bool,
+    /// Reading view styles
+    reading_mode: ReadingMode,
+    /// Tab IDs, unique across windows
+    tab_ids: TabIds,
+    /// Installed extensions, whose content scripts run on matching pages
+    #[cfg(feature = "extensions")]
+    extensions: ExtensionManager,
+    /// Extension APIs, background pages and popups
+    #[cfg(feature = "extensions")]
+    extension_runtime: ExtensionRuntime,
+    /// Tracking protection and extension request rules, a stage of the
+    /// network's interception pipeline
+    #[cfg(feature = "extensions")]
+    net_rules: Arc<NetRequestRules>,
+    /// Visited URLs for autocomplete and `:visited`
+    history: HistoryStore,
+    /// Page zoom per site
+    zoom: ZoomStore,
+    /// Installed web apps
+    web_apps: WebApps,
+    /// Engine flags from about:config
+    config: ConfigStore,
+    /// Event counts for diagnostics
+    telemetry: Telemetry,
+    /// Dictionary checks of editable content, shared by every window
+    spellchecker: SpellChecker,
+}
+
+impl Shared {
+    fn new() -> Self {
+        let mut network = NetworkManager::new();
+        let config = ConfigStore::new();
+        network.set_user_agent(&config.config().user_agent);
+        let security = SecurityManager::new();
+        network.set_privacy_signals(security.privacy_signals.clone());
+        network.set_content_blocking(security.content_blocking.clone());
+        #[cfg(feature = "extensions")]
+        let net_rules = Arc::new(NetRequestRules::new());
+        #[cfg(feature = "extensions")]
+        net_rules.set_tracking_protection(&security.tracking);
+        #[cfg(feature = "extensions")]
+        let network = network.with_interceptor(net_rules.clone());
+        let mut memory = MemoryIntegration::new();
+        memory.set_page_limit(config.config().max_memory);
+        // Pages render in the browser process; the model still decides which
+        // tabs share a renderer and its memory ceiling
+        let processes = ProcessArchitecture::from_config(config.config()).in_process();
+        
+        Self {
+            loader: Loader::new(),
+            network,
+            devtools: DevTools::new(),
+            _advanced_net: AdvancedNetworking::new(),
+            security,
+            memory,
+            processes,
+            save_data: false,
+            reading_mode: ReadingMode::new(),
+            tab_ids: TabIds::new(),
+            #[cfg(feature = "extensions")]
+            extensions: ExtensionManager::new(),
+            #[cfg(feature = "extensions")]
+            extension_runtime: ExtensionRuntime::new(),
+            #[cfg(feature = "extensions")]
+            net_rules,
+            history: HistoryStore::new(),
+            zoom: ZoomStore::new(),
+            web_apps: WebApps::new(),
+            config,
+            telemetry: Telemetry::new(),
+            spellchecker: SpellChecker::for_system_language(),
+        }
+    }
+    
+    /// Add a loaded page to the history
+    fn record_visit(&mut self, url: &str, title: &str, typed: bool) {
+        self.history.record_visit(url, title, typed);
+        self.history.save();
+    }
+    
+    /// Proceed to `host` despite its certificate error, unless it has HSTS
+    fn allow_invalid_certificate(&mut self, host: &str, code: &'static str) -> bool {
+        let allowed = self.network.allow_invalid_certificate(host, code);
+        if allowed {
+            log::warn!("Proceeding to {} despite {}", host, code);
+            self.telemetry.record(TelemetryEvent::CertificateErrorOverridden { code });
+        }
+        allowed
+    }
+    
+    /// Carry out a button press on an internal page
+    fn run_internal_action(&mut self, action: &InternalAction) {
+        let result = match action {
+            InternalAction::EvictCache(url) => {
+                if self.network.evict_cached(url) {
+                    log::info!("Evicted {} from the cache", url);
+                }
+                Ok(())
+            }
+            InternalAction::SetConfig(name, value) => self.config.set(name, value),
+            InternalAction::ResetConfig(name) => self.config.reset(name),
+        };
+        match result {
+            Ok(()) => self.apply_config(),
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+    
+    /// Put config flags the browser reads outside page loads into effect
+    fn apply_config(&mut self) {
+        let user_agent = self.config.config().user_agent.clone();
+        self.network.set_user_agent(&user_agent);
+        self.memory.set_page_limit(self.config.config().max_memory);
+        self.processes.set_process_model(self.config.config().process_model);
+    }
+    
+    /// Give a tab the renderer for the page it loaded
+    fn assign_renderer(&mut self, tab: TabId, url: &str) {
+        let id = fos_engine::TabId::new(tab);
+        self.processes.browser_mut().add_tab(id).url = url.to_string();
+        if let Err(e) = self.processes.assign_renderer(id, url) {
+            log::warn!("No renderer for tab {}: {}", tab, e);
+        }
+    }
+    
+    /// Let the site of a page load a category of third-party content, in
+    /// the renderer and the network alike
+    fn unblock_content(&mut self, page_url: &str, category: ContentCategory) {
+        if let Some(site) = self.security.content_blocking.unblock(page_url, category) {
+            log::info!("Unblocked third-party {:?} on {}", category, site);
+        }
+        self.network.set_content_blocking(self.security.content_blocking.clone());
+    }
+}
+
+/// Request from a window that the app carries out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WindowCommand {
+    /// Open a new window with a blank tab
+    NewWindow,
+    /// Move a tab into a new window of its own
+    MoveTabToNewWindow(TabId),
+    /// Close the window
+    Close,
+}
+
+/// A top-level window with its own tab strip, surface and shown page
+struct BrowserWindow {
     /// Window handle
-    window: Option<Arc<Window>>,
+    window: Arc<Window>,
     /// Surface for rendering
-    surface: Option<softbuffer::Surface<Arc<Window>, Arc<Window>>>,
+    surface: softbuffer::Surface<Arc<Window>, Arc<Window>>,
     /// Tab manager
     tabs: TabManager,
     /// UI chrome
     chrome: Chrome,
-    /// Page loader
-    loader: Loader,
     /// Page renderer
     renderer: PageRenderer,
     /// Current rendered page (cached)
     rendered_page: Option<RenderedPage>,
-    /// Initial URL
-    initial_url: String,
     /// Window dimensions
     width: u32,
     height: u32,
@@ -98,39 +450,62 @@ struct BrowserApp {
     mouse_y: i32,
     /// Resize is pending (for debouncing)
     resize_pending: bool,
-    /// Network manager with HTTP cache
-    network: NetworkManager,
     /// Current page with JavaScript runtime
     current_page: Option<Page>,
+    /// Child frames of the current page
+    frames: Frames,
     /// Last timer check time
     last_timer_check: std::time::Instant,
-    /// Developer tools
-    devtools: DevTools,
     /// Accessibility manager
     a11y: AccessibilityManager,
     /// Media manager
     media: MediaManager,
     /// Canvas manager
     canvas: CanvasManager,
-    /// Advanced networking (WebSocket, XHR, SSE)
-    _advanced_net: AdvancedNetworking,
-    /// Security manager (CSP, sandbox, privacy)
-    _security: SecurityManager,
-    /// Memory integration (pressure, hibernation)
-    _memory: MemoryIntegration,
+    /// Pointer lock (hidden, captured cursor)
+    pointer_lock: PointerLockManager,
+    /// Drags within the page and files dragged in from the OS
+    drag_drop: DragDropManager,
+    /// Input method composing text for the URL bar
+    ime: ImeManager,
+    /// Requests for the app, taken after each event
+    commands: Vec<WindowCommand>,
+    /// Document-idle content scripts wait for the first idle time after a
+    /// page loads
+    #[cfg(feature = "extensions")]
+    document_idle_pending: bool,
+    /// The page loading was typed into the URL bar
+    typed_navigation: bool,
+    /// Host and error code of the certificate error page shown, which may
+    /// offer to proceed
+    cert_error: Option<(String, &'static str)>,
+    /// Last click in the page, which lets its scripts open a tab
+    user_activation: Option<std::time::Instant>,
 }
 
-impl BrowserApp {
-    fn new(initial_url: String) -> Self {
+impl BrowserWindow {
+    fn new(
+        window: Arc<Window>,
+        surface: softbuffer::Surface<Arc<Window>, Arc<Window>>,
+        tabs: TabManager,
+        shared: &Shared,
+    ) -> Self {
+        let mut renderer = PageRenderer::new(800, 600);
+        renderer.set_prefers_reduced_data(shared.save_data);
+        renderer.set_visited_links(shared.history.visited_links());
+        renderer.set_spellchecker(shared.spellchecker.clone());
+        let mut pointer_lock = PointerLockManager::new();
+        pointer_lock.set_capture(Box::new(WindowCursor(window.clone())));
+        let mut ime = ImeManager::new();
+        ime.set_context(Box::new(WindowIme(window.clone())));
+        
         Self {
-            window: None,
-            surface: None,
-            tabs: TabManager::new(),
+            window,
+            surface,
+            tabs,
             chrome: Chrome::new(),
-            loader: Loader::new(),
-            renderer: PageRenderer::new(800, 600),
+            renderer,
             rendered_page: None,
-            initial_url,
             width: 1024,
             height: 768,
             modifiers: winit::keyboard::ModifiersState::default(),
@@ -144,21 +519,102 @@ impl BrowserApp {
             mouse_x: 0,
             mouse_y: 0,
             resize_pending: false,
-            network: NetworkManager::new(),
             current_page: None,
+            frames: Frames::default(),
             last_timer_check: std::time::Instant::now(),
-            devtools: DevTools::new(),
             a11y: AccessibilityManager::new(),
             media: MediaManager::new(),
             canvas: CanvasManager::new(),
-            _advanced_net: AdvancedNetworking::new(),
-            _security: SecurityManager::new(),
-            _memory: MemoryIntegration::new(),
+            pointer_lock,
+            drag_drop: DragDropManager::new(),
+            ime,
+            commands: Vec::new(),
+            #[cfg(feature = "extensions")]
+            document_idle_pending: false,
+            typed_navigation: false,
+            cert_error: None,
+            user_activation: None,
+        }
+    }
+    
+    /// Apply the data saver preference to styling and scripts
+    fn set_save_data(&mut self, enabled: bool) {
+        self.renderer.set_prefers_reduced_data(enabled);
+        if let Some(runtime) = self.current_page.as_mut().and_then(|p| p.js_runtime.as_mut()) {
+            runtime.set_save_data(enabled);
+        }
+    }
+    
+    /// Approximate bytes held by the window's tabs and shown page
+    fn memory_usage(&self) -> usize {
+        let tabs: usize = self.tabs.tabs_in_order().iter().map(|t| t.memory_usage()).sum();
+        tabs + self.current_html.len() + self.rendered_page.as_ref().map_or(0, |r| r.pixels.len())
+    }
+    
+    /// DOM nodes of the shown page
+    fn dom_nodes(&self) -> usize {
+        self.current_page.as_ref()
+            .and_then(|p| p.document())
+            .map_or(0, |doc| doc.lock().unwrap().tree().len())
+    }
+    
+    /// Forget the shown page after its tab left the window
+    fn drop_current_page(&mut self) {
+        self.current_page = None;
+        self.frames = Frames::default();
+        self.rendered_page = None;
+        self.current_html.clear();
+        self.current_url.clear();
+        self.needs_reload = true;
+    }
+    
+    /// Current session, with the shown page's scroll offset and form values
+    fn session_state(&self) -> SessionState {
+        let mut state = self.tabs.session_state();
+        let Some(tab) = self.tabs.active_tab().filter(|t| t.pending_restore.is_none()) else {
+            return state;
+        };
+        if let Some(saved) = state.tabs.iter_mut().find(|t| t.id == tab.id) {
+            saved.scroll = ScrollOffset::new(0.0, self.scroll_offset);
+            if let Some(doc) = self.current_page.as_ref().and_then(|p| p.document()) {
+                saved.form_fields = capture_form_fields(doc.lock().unwrap().tree());
+            }
+        }
+        state
+    }
+    
+    /// Inject the content scripts due at one point of the current page's
+    /// load
+    #[cfg(feature = "extensions")]
+    fn inject_content_scripts(&mut self, shared: &Shared, run_at: RunAt) {
+        if let Some(ref mut page) = self.current_page {
+            page.inject_content_scripts(&shared.extensions, run_at);
+        }
+    }
+    
+    /// Give each extension's isolated world on the current page its
+    /// `chrome` object, before any content script runs
+    #[cfg(feature = "extensions")]
+    fn install_extension_apis(&mut self, shared: &Shared) {
+        let Some(tab) = self.tabs.active_tab().map(|t| t.id) else { return };
+        let Some(runtime) = self.current_page.as_mut().and_then(|p| p.js_runtime.as_mut()) else { return };
+        for ext in shared.extensions.enabled() {
+            let ext = ext.lock().unwrap();
+            if !ext.manifest.content_scripts.is_empty() {
+                runtime.set_world_api(&ext.id, shared.extension_runtime.api(&ext, ExtensionContext::ContentScript(tab)));
+            }
         }
     }
     
     /// Load the current tab's page
-    fn load_current_page(&mut self) {
+    fn load_current_page(&mut self, shared: &mut Shared) {
+        // A hibernated tab reloads from the network when shown again
+        if let Some(tab) = self.tabs.active_tab_mut().filter(|t| t.hibernated) {
+            tab.hibernated = false;
+            let id = tab.id as u64;
+            shared.memory.wake_tab(id);
+        }
+        
         // Get tab info
         let (url, needs_network, cached_html) = match self.tabs.active_tab() {
             Some(tab) => (tab.url.clone(), tab.needs_network_load, tab.cached_html.clone()),
@@ -166,6 +622,7 @@ impl BrowserApp {
         };
         
         log::info!("Loading: {} (network: {})", url, needs_network);
+        self.cert_error = None;
         
         // If we have cached HTML and don't need network, just re-render
         if let Some(ref html) = cached_html {
@@ -173,7 +630,7 @@ impl BrowserApp {
                 log::info!("Using cached HTML ({} bytes)", html.len());
                 // Reset scroll only if URL changed (not resize)
                 let reset_scroll = self.current_url != url;
-                self.render_page(html, &url, reset_scroll);
+                self.render_page(shared, html, &url, reset_scroll);
                 self.needs_reload = false;
                 return;
             }
@@ -181,16 +638,52 @@ impl BrowserApp {
         
         // Try network cache first, then fetch
         // Log to DevTools network panel
-        let request_id = self.devtools.log_request(&url, "GET");
-        let fetch_result = self.network.fetch_html(&url);
+        let request_id = shared.devtools.log_request(&url, "GET");
+        shared.network.set_response_overrides(shared.devtools.network.overrides().to_vec());
+        let tab_id = self.tabs.active_tab().map_or(0, |t| t.id as u64);
+        let fetch_result = if url.starts_with("about:") {
+            // Internal pages are generated here and shown like any other;
+            // only the user typing one runs the action in its URL
+            if self.typed_navigation {
+                if let Some(action) = InternalAction::parse(&url) {
+                    shared.run_internal_action(&action);
+                }
+            }
+            let html = shared.loader.internal_page_html(&url, &self.internal_state(shared));
+            shared.devtools.log_fetch_response(request_id, 200, &[], html.as_bytes(), None);
+            Ok(html)
+        } else {
+            // A service worker controlling the page answers before the network
+            let fetched = match shared.loader.fetch_through_worker(&url, &mut shared.network) {
+                Some(fetched) => fetched,
+                None => shared.network.fetch(&url, None),
+            };
+            fetched.and_then(|result| {
+                // Log the response with its headers, body and timing
+                shared.devtools.log_fetch_response(request_id, result.status, &result.headers, &result.body, result.timings);
+                // Every load here comes from the user: the URL bar, a link, history
+                for site in shared.security.record_navigation(tab_id, &url, true, &result.headers) {
+                    log::info!("Bounce tracker detected: {}", site);
+                }
+                String::from_utf8(result.body).map_err(|e| NetworkError::InvalidEncoding(e.to_string()))
+            })
+        };
         
         match fetch_result {
             Ok(html) => {
-                // Log successful response
-                self.devtools.log_response(request_id, 200, "OK");
-                
                 // Create page with JavaScript runtime
                 let mut page = Page::from_html(&url, html.clone());
+                if let Some(runtime) = page.js_runtime.as_mut() {
+                    runtime.set_save_data(shared.save_data);
+                    runtime.set_device_info(shared.security.device_info(&url));
+                    runtime.set_storage_access(shared.security.storage_access.clone());
+                }
+                
+                // Refill form fields of a tab restored from the last session
+                let pending_restore = self.tabs.active_tab_mut().and_then(|t| t.pending_restore.take());
+                if let (Some(restore), Some(doc)) = (&pending_restore, page.document()) {
+                    restore_form_fields(doc.lock().unwrap().tree_mut(), &restore.form_fields);
+                }
                 
                 // Update tab with loaded content and cache the HTML
                 if let Some(tab) = self.tabs.active_tab_mut() {
@@ -199,24 +692,68 @@ impl BrowserApp {
                     tab.cached_html = Some(html.clone());
                     tab.needs_network_load = false;
                 }
+                if let Some(id) = self.tabs.active_tab().map(|t| t.id) {
+                    shared.assign_renderer(id, &url);
+                }
+                let title = page.title.clone().unwrap_or_default();
+                shared.record_visit(&url, &title, std::mem::take(&mut self.typed_navigation));
                 
-                // Initialize JavaScript (if scripts exist)
-                if let Err(e) = page.initialize_javascript() {
+                // Initialize JavaScript (if scripts exist and may run)
+                if !shared.config.config().enable_javascript {
+                    page.js_runtime = None;
+                } else if let Err(e) = page.initialize_javascript() {
                     log::warn!("Failed to initialize JavaScript: {}", e);
-                    self.devtools.warn(&format!("JS init failed: {}", e));
+                    shared.devtools.warn(&format!("JS init failed: {}", e));
+                } else if let Some(runtime) = page.js_runtime.as_ref() {
+                    runtime.install_host_api(&registration_api(shared.loader.service_workers(), &url));
+                }
+                
+                // Load the page's iframes, at their entries in the tab's
+                // history, which run their scripts now; what they post
+                // waits for the page's own scripts
+                if let Some(tab) = self.tabs.active_tab_mut() {
+                    self.frames.load(&mut tab.frame_tree, &mut page, frame_fetcher(shared, &url));
                 }
                 
                 // Store the page
                 self.current_page = Some(page);
                 
-                // Reset scroll for new page loads
-                self.render_page(&html, &url, true);
+                // Content scripts at document start, before any page script
+                #[cfg(feature = "extensions")]
+                {
+                    self.install_extension_apis(shared);
+                    self.inject_content_scripts(shared, RunAt::DocumentStart);
+                }
+                
+                // Reset scroll for new page loads; restored tabs return to
+                // their saved offset
+                match pending_restore.map(|r| r.scroll.y).filter(|y| *y > 0.0) {
+                    Some(y) => {
+                        let viewport_height = self.height.saturating_sub(URL_BAR_HEIGHT) as f32;
+                        self.scroll_offset = y;
+                        self.render_start_y = (y - viewport_height * 2.0).max(0.0);
+                        self.render_page(shared, &html, &url, false);
+                    }
+                    None => self.render_page(shared, &html, &url, true),
+                }
                 
-                // Execute scripts after initial render
-                if let Some(ref mut page) = self.current_page {
-                    if let Err(e) = page.execute_scripts() {
+                // Execute scripts after initial render, unless JavaScript
+                // is turned off
+                if let Some(page) = self.current_page.as_mut().filter(|page| page.js_runtime.is_some()) {
+                    let span = shared.devtools.profiler.begin_trace(script_task(ScriptEventType::EvaluateScript, &url));
+                    let result = page.execute_scripts();
+                    shared.devtools.profiler.end_trace(span);
+                    if let Err(e) = result {
                         log::warn!("Failed to execute scripts: {}", e);
-                        self.devtools.error(&format!("Script error: {}", e));
+                        shared.devtools.error(&format!("Script error: {}", e));
+                    }
+                    self.frames.dispatch(page);
+                    
+                    // Content scripts at document end, once the DOM is complete
+                    #[cfg(feature = "extensions")]
+                    {
+                        page.inject_content_scripts(&shared.extensions, RunAt::DocumentEnd);
+                        self.document_idle_pending = true;
                     }
                     
                     // Build accessibility tree and extract media/canvas from DOM
@@ -229,7 +766,10 @@ impl BrowserApp {
                         log::info!("Built a11y tree: {} focusable elements, {} links", 
                             a11y_stats.focusable_count, a11y_stats.link_count);
                         
-                        // Media elements
+                        // Media elements (audio routed to the active tab's group)
+                        if let Some(tab) = self.tabs.active_tab() {
+                            self.media.set_current_tab(tab.id as u64);
+                        }
                         self.media.extract_from_document(&doc_guard);
                         let media_stats = self.media.stats();
                         if media_stats.video_count > 0 || media_stats.audio_count > 0 {
@@ -238,6 +778,7 @@ impl BrowserApp {
                         }
                         
                         // Canvas elements
+                        self.canvas.set_page(&url, shared.security.fingerprinting.clone());
                         self.canvas.extract_from_document(&doc_guard);
                         let canvas_stats = self.canvas.stats();
                         if canvas_stats.canvas_count > 0 {
@@ -247,12 +788,16 @@ impl BrowserApp {
                     }
                 }
             }
+            Err(NetworkError::Tls(error)) if error.is_certificate_error() => {
+                shared.devtools.log_network_error(request_id, &error.to_string());
+                self.show_certificate_error(shared, &url, &error);
+            }
             Err(e) => {
                 // Log failed request
-                self.devtools.log_network_error(request_id, &e.to_string());
+                shared.devtools.log_network_error(request_id, &e.to_string());
                 // Network cache failed, try loader as fallback
                 log::warn!("Network fetch failed, trying loader: {}", e);
-                match self.loader.load_sync(&url) {
+                match shared.loader.load_sync(&url) {
                     Ok(page) => {
                         // Update tab with loaded content and cache the HTML
                         if let Some(tab) = self.tabs.active_tab_mut() {
@@ -261,9 +806,11 @@ impl BrowserApp {
                             tab.cached_html = Some(page.html.clone());
                             tab.needs_network_load = false;
                         }
+                        let title = page.title.clone().unwrap_or_default();
+                        shared.record_visit(&url, &title, std::mem::take(&mut self.typed_navigation));
                         
                         // Reset scroll for new page loads
-                        self.render_page(&page.html, &url, true);
+                        self.render_page(shared, &page.html, &url, true);
                     }
                     Err(e) => {
                         log::error!("Failed to load {}: {}", url, e);
@@ -292,9 +839,58 @@ impl BrowserApp {
         self.needs_reload = false;
     }
     
+    /// Browser state for the internal pages: memory of every window and
+    /// of this window's tabs, the HTTP cache and config flags
+    fn internal_state(&self, shared: &Shared) -> InternalState {
+        let active = self.tabs.active_tab().map(|t| t.id);
+        let tabs = self.tabs.tabs_in_order().into_iter().map(|tab| {
+            let mut report = tab.memory_report();
+            // The shown page is held by the window, not the tab
+            if Some(tab.id) == active {
+                let shown = self.current_html.len() + self.rendered_page.as_ref().map_or(0, |r| r.pixels.len());
+                report.tiers.hot_count += 1;
+                report.tiers.hot_bytes += shown;
+                report.tiers.total_bytes += shown;
+                report.stats.heap_used += shown;
+                report.stats.dom_nodes = self.dom_nodes();
+            }
+            report
+        }).collect();
+        InternalState {
+            memory: shared.memory.manager.stats.clone(),
+            tabs,
+            cache: shared.network.cached_resources(),
+            config: shared.config.flags(),
+        }
+    }
+    
+    /// Show the certificate error page in place of `url`; a reload tries
+    /// the connection again
+    fn show_certificate_error(&mut self, shared: &mut Shared, url: &str, error: &TlsError) {
+        log::warn!("Certificate error for {}: {}", url, error);
+        let host = fos_engine::url::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let hsts = shared.network.is_hsts_host(&host);
+        let overridable = error.is_overridable() && !hsts;
+        shared.telemetry.record(TelemetryEvent::CertificateErrorShown { code: error.code(), overridable });
+        
+        let page = shared.loader.certificate_error_page(url, error, hsts);
+        if let Some(tab) = self.tabs.active_tab_mut() {
+            tab.title = page.title.clone().unwrap_or_default();
+            tab.loading = false;
+        }
+        self.current_page = None;
+        self.render_page(shared, &page.html, url, true);
+        if overridable {
+            self.cert_error = Some((host, error.code()));
+        }
+    }
+    
     /// Render a page from HTML (helper for caching)
     /// If reset_scroll is false, keeps current scroll position (for resize)
-    fn render_page(&mut self, html: &str, url: &str, reset_scroll: bool) {
+    fn render_page(&mut self, shared: &mut Shared, html: &str, url: &str, reset_scroll: bool) {
         let content_width = self.width.saturating_sub(TAB_BAR_WIDTH);
         let content_height = self.height.saturating_sub(URL_BAR_HEIGHT);
         
@@ -302,25 +898,77 @@ impl BrowserApp {
         // Links will be re-captured during scroll when re-rendering is triggered
         let render_height = content_height * 5;
         self.renderer.set_viewport(content_width, render_height);
+        self.renderer.set_device_pixel_ratio(self.window.scale_factor() as f32);
+        self.renderer.set_text_scaling(self.zoom_settings(shared, url));
         
         log::info!("Rendering {} bytes of HTML...", html.len());
         self.current_html = html.to_string();
         self.current_url = url.to_string();
+        self.update_title(shared);
         
         if reset_scroll {
             self.scroll_offset = 0.0;
             self.render_start_y = 0.0;
         }
         
-        self.rendered_page = self.renderer.render_html(html, url, self.render_start_y);
+        self.renderer.set_content_blocking(shared.security.content_blocking.clone());
+        
+        // Child frames composite into the page; pages shown without
+        // loading, such as error pages, have none
+        if self.frames.url() != url {
+            self.frames = Frames::new(url);
+        }
+        self.frames.render(&mut self.renderer);
+        
+        self.rendered_page = self.renderer.render_html_traced(
+            html,
+            url,
+            self.render_start_y,
+            shared.devtools.profiler.tracer_mut(),
+        );
         
         if let Some(ref rendered) = self.rendered_page {
             log::info!("Rendered: {}x{} pixels", rendered.width, rendered.height);
         }
     }
     
+    /// Page zoom of the site at `url` with the active tab's text zoom
+    fn zoom_settings(&self, shared: &Shared, url: &str) -> TextScalingSettings {
+        TextScalingSettings {
+            page_zoom: ZoomLevel::new(shared.zoom.get(url)),
+            text_zoom: self.tabs.active_tab().map(|tab| tab.text_zoom).unwrap_or_default(),
+            ..self.renderer.text_scaling().clone()
+        }
+    }
+    
+    /// Zoom the current site, or with `text_only` just the text of the
+    /// active tab, and re-render in place
+    fn zoom(&mut self, shared: &mut Shared, step: ZoomStep, text_only: bool) {
+        if text_only {
+            let Some(tab) = self.tabs.active_tab_mut() else { return };
+            tab.text_zoom = ZoomLevel::new(step.apply(tab.text_zoom.factor));
+            log::info!("Text zoom {}%", tab.text_zoom.percentage());
+        } else {
+            let factor = step.apply(shared.zoom.get(&self.current_url));
+            shared.zoom.set(&self.current_url, factor);
+            log::info!("Page zoom {}%", ZoomLevel::new(factor).percentage());
+        }
+        
+        // Keep the same part of the page in view
+        let old_ratio = self.renderer.css_pixel_ratio();
+        let settings = self.zoom_settings(shared, &self.current_url);
+        if self.renderer.set_text_scaling(settings) && !self.current_html.is_empty() {
+            let ratio = self.renderer.css_pixel_ratio() / old_ratio;
+            self.scroll_offset *= ratio;
+            self.render_start_y *= ratio;
+            let (html, url) = (self.current_html.clone(), self.current_url.clone());
+            self.render_page(shared, &html, &url, false);
+        }
+        self.request_redraw();
+    }
+    
     /// Process JavaScript timers (call periodically)
-    fn process_js_timers(&mut self) {
+    fn process_js_timers(&mut self, shared: &mut Shared) {
         // Check every 16ms (60fps)
         if self.last_timer_check.elapsed() < std::time::Duration::from_millis(16) {
             return;
@@ -329,17 +977,87 @@ impl BrowserApp {
         
         if let Some(ref mut page) = self.current_page {
             if page.has_pending_timers() {
-                if let Err(e) = page.process_timers() {
+                let span = shared.devtools.profiler.begin_trace(script_task(ScriptEventType::TimerFire, &self.current_url));
+                let result = page.process_timers();
+                shared.devtools.profiler.end_trace(span);
+                if let Err(e) = result {
                     log::warn!("Timer processing error: {}", e);
                 }
                 // Request redraw if timers ran (DOM might have changed)
                 self.request_redraw();
             }
         }
+        
+        // Frames' timers, then the messages they and the page posted
+        self.frames.process_timers();
+        let delivered = self.current_page.as_ref().map_or(0, |page| self.frames.dispatch(page));
+        if delivered > 0 {
+            self.request_redraw();
+        }
+        
+        // Windows the page and its frames opened
+        for OpenRequest { source, url, target } in self.frames.take_open_requests() {
+            self.open_target(shared, source, &url, &target, true);
+        }
+        
+        // Badges set by the page's scripts
+        let badge = self.current_page.as_ref()
+            .and_then(|page| page.js_runtime.as_ref())
+            .and_then(|runtime| runtime.take_app_badge());
+        if let Some(badge) = badge {
+            if shared.web_apps.set_badge(&self.current_url, badge) {
+                self.update_title(shared);
+            }
+        }
+    }
+    
+    /// Install the web app whose manifest the page links to
+    fn install_app(&mut self, shared: &mut Shared) {
+        let href = self.current_page.as_ref()
+            .and_then(Page::document)
+            .and_then(|document| manifest_link(&document.lock().unwrap()));
+        let Some(href) = href else {
+            log::info!("{} has no web app manifest", self.current_url);
+            return;
+        };
+        let Some(manifest_url) = fos_engine::url::Url::parse(&self.current_url).ok()
+            .and_then(|base| base.join(&href).ok())
+            .map(|url| url.as_str()) else { return };
+        
+        match shared.network.fetch(&manifest_url, Some(&self.current_url)) {
+            Ok(result) if (200..300).contains(&result.status) => {
+                let json = String::from_utf8_lossy(&result.body);
+                match shared.web_apps.install(&self.current_url, &manifest_url, &json) {
+                    Ok(app) => log::info!("Installed {} with {} shortcuts", app.name, app.shortcuts.len()),
+                    Err(e) => log::warn!("Can't install {}: {}", manifest_url, e),
+                }
+            }
+            Ok(result) => log::warn!("Manifest {} failed with HTTP {}", manifest_url, result.status),
+            Err(e) => log::warn!("Failed to fetch manifest {}: {}", manifest_url, e),
+        }
+        self.update_title(shared);
+    }
+    
+    /// Title the window, with the badge of the app the page belongs to
+    fn update_title(&self, shared: &Shared) {
+        let badge = shared.web_apps.app_for_url(&self.current_url).map_or(AppBadge::None, |app| app.badge);
+        let title = match badge {
+            AppBadge::None => WINDOW_TITLE.to_string(),
+            AppBadge::Flag => format!("• {}", WINDOW_TITLE),
+            AppBadge::Count(count) => format!("({}) {}", count, WINDOW_TITLE),
+        };
+        self.window.set_title(&title);
     }
     
     /// Render the browser UI and content
-    fn render(&mut self) {
+    fn render(&mut self, shared: &mut Shared) {
+        shared.devtools.profiler.begin_frame();
+        self.render_frame(shared);
+        shared.devtools.profiler.end_frame();
+    }
+    
+    /// Produce one frame: swap in background renders, reload if needed and composite
+    fn render_frame(&mut self, shared: &mut Shared) {
         // Check for completed background render
         if let Some(ref rx) = self.bg_render_rx {
             match rx.try_recv() {
@@ -363,12 +1081,10 @@ impl BrowserApp {
         
         // Load page if needed
         if self.needs_reload {
-            self.load_current_page();
+            self.load_current_page(shared);
         }
         
-        let Some(window) = &self.window else { return };
-        
-        let size = window.inner_size();
+        let size = self.window.inner_size();
         if size.width == 0 || size.height == 0 {
             return;
         }
@@ -386,12 +1102,11 @@ impl BrowserApp {
         if self.resize_pending && self.bg_render_rx.is_none() {
             self.resize_pending = false;
             self.needs_reload = true;
-            self.load_current_page();
+            self.load_current_page(shared);
         }
         
-        let Some(surface) = &mut self.surface else { return };
-        
         // Resize surface if needed
+        let surface = &mut self.surface;
         let _ = surface.resize(
             NonZeroU32::new(size.width).unwrap(),
             NonZeroU32::new(size.height).unwrap(),
@@ -402,6 +1117,7 @@ impl BrowserApp {
             Ok(b) => b,
             Err(_) => return,
         };
+        let composite = shared.devtools.profiler.begin_trace(TraceKind::Composite);
         
         let buffer_width = size.width as usize;
         let buffer_height = size.height as usize;
@@ -473,6 +1189,32 @@ impl BrowserApp {
             }
         }
         
+        // Tab strip audio indicators follow the media mixer
+        let tab_ids: Vec<u64> = self.tabs.tabs_in_order().iter().map(|t| t.id as u64).collect();
+        self.media.retain_tabs(&tab_ids);
+        for id in tab_ids {
+            let audible = self.media.is_tab_audible(id);
+            let muted = self.media.is_tab_muted(id);
+            let capturing = self.media.is_tab_capturing(id);
+            if let Some(tab) = self.tabs.get_mut(id as u32) {
+                tab.audible = audible;
+                tab.audio_muted = muted;
+                tab.capturing = capturing;
+            }
+        }
+        
+        // Captured tabs receive the page area of the composited frame
+        if self.media.has_tab_captures() && buffer_width > content_x {
+            if let Some(tab) = self.tabs.active_tab() {
+                let width = buffer_width.saturating_sub(content_x);
+                let height = content_height.min(buffer_height);
+                let pixels: Vec<u32> = (0..height)
+                    .flat_map(|y| buffer[y * buffer_width + content_x..(y + 1) * buffer_width].iter().copied())
+                    .collect();
+                self.media.push_tab_frame(tab.id as u64, &pixels, width as u32, height as u32);
+            }
+        }
+        
         // Render UI chrome on top
         self.chrome.render(
             &mut buffer,
@@ -483,22 +1225,43 @@ impl BrowserApp {
         
         // Present
         let _ = buffer.present();
+        shared.devtools.profiler.end_trace(composite);
     }
     
     /// Handle keyboard input
-    fn handle_key(&mut self, event: KeyEvent, modifiers: &winit::keyboard::ModifiersState) {
+    fn handle_key(&mut self, shared: &mut Shared, event: KeyEvent, modifiers: &winit::keyboard::ModifiersState) {
         if event.state != ElementState::Pressed {
             return;
         }
         
+        // Escape always releases pointer lock and is not seen by the page
+        if event.physical_key == PhysicalKey::Code(KeyCode::Escape) && self.pointer_lock.handle_escape() {
+            self.dispatch_pointer_lock_events();
+            return;
+        }
+        
         let ctrl = modifiers.control_key();
         
+        // Chrome keyboard model (F6 regions, tab strip, menus) gets the first look
+        if let Some(key) = self.chrome_shortcut(&event, modifiers) {
+            if let Some(command) = self.chrome.handle_a11y_key(&key, &mut self.tabs) {
+                self.handle_chrome_command(shared, command);
+                return;
+            }
+        }
+        
+        // Keys go to the input method while it composes
+        if self.ime.is_composing() {
+            return;
+        }
+        
         // If URL bar is focused, handle text input
         if self.chrome.is_url_bar_focused() {
             match event.physical_key {
                 PhysicalKey::Code(KeyCode::Enter) => {
                     if let Some(url) = self.chrome.handle_enter() {
                         self.navigate_to(&url);
+                        self.typed_navigation = true;
                     }
                     self.request_redraw();
                     return;
@@ -508,6 +1271,16 @@ impl BrowserApp {
                     self.request_redraw();
                     return;
                 }
+                PhysicalKey::Code(KeyCode::ArrowUp) => {
+                    self.chrome.handle_up_down(-1);
+                    self.request_redraw();
+                    return;
+                }
+                PhysicalKey::Code(KeyCode::ArrowDown) => {
+                    self.chrome.handle_up_down(1);
+                    self.request_redraw();
+                    return;
+                }
                 PhysicalKey::Code(KeyCode::Backspace) => {
                     self.chrome.handle_backspace();
                     self.request_redraw();
@@ -542,10 +1315,11 @@ impl BrowserApp {
                     // Handle text input
                     if let Some(text) = &event.text {
                         for c in text.chars() {
-                            if c.is_ascii_graphic() || c == ' ' {
+                            if !c.is_control() {
                                 self.chrome.handle_char(c);
                             }
                         }
+                        self.suggest_urls(shared);
                         self.request_redraw();
                         return;
                     }
@@ -563,11 +1337,24 @@ impl BrowserApp {
                 self.request_redraw();
             }
             PhysicalKey::Code(KeyCode::KeyW) if ctrl => {
-                // Ctrl+W: Close tab
+                // Ctrl+W: Close tab, and the window with its last tab
                 self.tabs.close_active_tab();
+                if self.tabs.count() == 0 {
+                    self.commands.push(WindowCommand::Close);
+                }
                 self.needs_reload = true;
                 self.request_redraw();
             }
+            PhysicalKey::Code(KeyCode::KeyN) if ctrl && modifiers.shift_key() => {
+                // Ctrl+Shift+N: Move the tab to a new window
+                if let Some(tab) = self.tabs.active_tab() {
+                    self.commands.push(WindowCommand::MoveTabToNewWindow(tab.id));
+                }
+            }
+            PhysicalKey::Code(KeyCode::KeyN) if ctrl => {
+                // Ctrl+N: New window
+                self.commands.push(WindowCommand::NewWindow);
+            }
             PhysicalKey::Code(KeyCode::KeyO) if ctrl => {
                 // Ctrl+O: Go to tab above (previous)
                 self.tabs.select_previous_tab();
@@ -591,47 +1378,78 @@ impl BrowserApp {
             // Navigation (history)
             PhysicalKey::Code(KeyCode::KeyK) if ctrl => {
                 // Ctrl+K: Go back in history
-                if let Some(tab) = self.tabs.active_tab_mut() {
-                    if tab.go_back().is_some() {
-                        self.needs_reload = true;
-                    }
-                }
-                self.request_redraw();
+                self.traverse_history(shared, true);
             }
             PhysicalKey::Code(KeyCode::Semicolon) if ctrl => {
                 // Ctrl+; (Ñ on Spanish keyboard): Go forward in history
-                if let Some(tab) = self.tabs.active_tab_mut() {
-                    if tab.go_forward().is_some() {
-                        self.needs_reload = true;
-                    }
-                }
-                self.request_redraw();
+                self.traverse_history(shared, false);
             }
             PhysicalKey::Code(KeyCode::BracketLeft) if ctrl => {
                 // Ctrl+[: Alternative go back
-                if let Some(tab) = self.tabs.active_tab_mut() {
-                    if tab.go_back().is_some() {
-                        self.needs_reload = true;
-                    }
-                }
-                self.request_redraw();
+                self.traverse_history(shared, true);
             }
             PhysicalKey::Code(KeyCode::BracketRight) if ctrl => {
                 // Ctrl+]: Alternative go forward
+                self.traverse_history(shared, false);
+            }
+            
+            // Zoom: Ctrl+Plus/Minus/0 for the page, with Alt for text only
+            PhysicalKey::Code(KeyCode::Equal | KeyCode::NumpadAdd) if ctrl => {
+                self.zoom(shared, ZoomStep::In, modifiers.alt_key());
+            }
+            PhysicalKey::Code(KeyCode::Minus | KeyCode::NumpadSubtract) if ctrl => {
+                self.zoom(shared, ZoomStep::Out, modifiers.alt_key());
+            }
+            PhysicalKey::Code(KeyCode::Digit0 | KeyCode::Numpad0) if ctrl => {
+                self.zoom(shared, ZoomStep::Reset, modifiers.alt_key());
+            }
+            
+            // Page actions
+            PhysicalKey::Code(KeyCode::KeyR) if ctrl && modifiers.alt_key() => {
+                // Ctrl+Alt+R: Enter or leave reader mode
                 if let Some(tab) = self.tabs.active_tab_mut() {
-                    if tab.go_forward().is_some() {
+                    let changed = if tab.is_reader_mode() {
+                        tab.exit_reader_mode()
+                    } else {
+                        tab.enter_reader_mode(shared.reading_mode.settings())
+                    };
+                    if changed {
                         self.needs_reload = true;
+                    } else {
+                        log::info!("No article found for reader mode");
                     }
                 }
                 self.request_redraw();
             }
-            
-            // Page actions
             PhysicalKey::Code(KeyCode::KeyR) if ctrl => {
                 // Ctrl+R: Reload
                 self.needs_reload = true;
                 self.request_redraw();
             }
+            PhysicalKey::Code(KeyCode::KeyE) if ctrl && shared.devtools.is_open() => {
+                // Ctrl+E: Start/stop a performance recording, saved for Perfetto
+                if let Some(trace) = shared.devtools.toggle_recording() {
+                    let path = std::env::temp_dir().join("fos-trace.json");
+                    match std::fs::write(&path, trace) {
+                        Ok(()) => shared.devtools.log(&format!("Trace saved to {}", path.display())),
+                        Err(e) => shared.devtools.error(&format!("Failed to save trace: {}", e)),
+                    }
+                } else {
+                    shared.devtools.log("Performance recording started");
+                }
+                self.request_redraw();
+            }
+            PhysicalKey::Code(KeyCode::KeyM) if ctrl && shared.devtools.is_open() => {
+                // Ctrl+M: Heap snapshot; Ctrl+Shift+M: start/stop allocation sampling
+                if let Some(runtime) = self.current_page.as_ref().and_then(|p| p.js_runtime.as_ref()) {
+                    if modifiers.shift_key() {
+                        shared.devtools.toggle_allocation_sampling(runtime);
+                    } else {
+                        shared.devtools.take_heap_snapshot(runtime);
+                    }
+                }
+                self.request_redraw();
+            }
             PhysicalKey::Code(KeyCode::F5) => {
                 // F5: Reload
                 self.needs_reload = true;
@@ -639,17 +1457,18 @@ impl BrowserApp {
             }
             PhysicalKey::Code(KeyCode::F12) => {
                 // F12: Toggle DevTools
-                self.devtools.toggle();
-                if self.devtools.is_open() {
+                shared.devtools.toggle();
+                if shared.devtools.is_open() {
                     // Log to console when opening
-                    self.devtools.log("DevTools opened");
+                    shared.devtools.log("DevTools opened");
                     // Inspect current page DOM
                     if let Some(ref page) = self.current_page {
                         if let Some(doc) = page.document() {
                             let doc_guard = doc.lock().unwrap();
-                            self.devtools.inspect_document(&doc_guard);
+                            shared.devtools.inspect_document(&doc_guard);
                         }
                     }
+                    shared.devtools.inspect_accessibility(&self.a11y);
                 }
                 self.request_redraw();
             }
@@ -724,6 +1543,68 @@ impl BrowserApp {
         }
     }
     
+    /// Translate a key event for the chrome accessibility model.
+    /// While the URL bar is being edited only region/menu keys are forwarded.
+    fn chrome_shortcut(&self, event: &KeyEvent, modifiers: &winit::keyboard::ModifiersState) -> Option<KeyboardShortcut> {
+        let PhysicalKey::Code(code) = event.physical_key else {
+            return None;
+        };
+        
+        let name = match code {
+            KeyCode::F6 => "F6",
+            KeyCode::F10 => "F10",
+            KeyCode::KeyF if modifiers.alt_key() => "f",
+            _ if self.chrome.is_url_bar_focused() => return None,
+            KeyCode::ArrowUp => "ArrowUp",
+            KeyCode::ArrowDown => "ArrowDown",
+            KeyCode::ArrowLeft => "ArrowLeft",
+            KeyCode::ArrowRight => "ArrowRight",
+            KeyCode::Home => "Home",
+            KeyCode::End => "End",
+            KeyCode::Enter => "Enter",
+            KeyCode::Space => "Space",
+            KeyCode::Delete => "Delete",
+            KeyCode::Escape => "Escape",
+            _ => return None,
+        };
+        
+        let mut key = KeyboardShortcut::new(name);
+        key.ctrl = modifiers.control_key();
+        key.alt = modifiers.alt_key();
+        key.shift = modifiers.shift_key();
+        key.meta = modifiers.super_key();
+        Some(key)
+    }
+    
+    /// Apply a command produced by keyboard use of the chrome
+    fn handle_chrome_command(&mut self, shared: &mut Shared, command: ChromeCommand) {
+        match command {
+            ChromeCommand::Redraw | ChromeCommand::FocusContent => {}
+            ChromeCommand::TabsChanged | ChromeCommand::Reload => {
+                self.needs_reload = true;
+            }
+            ChromeCommand::ToggleDevTools => {
+                shared.devtools.toggle();
+            }
+            ChromeCommand::InstallApp => {
+                self.install_app(shared);
+            }
+            ChromeCommand::ToggleTabMute(id) => {
+                let muted = !self.media.is_tab_muted(id as u64);
+                self.media.set_tab_muted(id as u64, muted);
+            }
+        }
+        self.request_redraw();
+    }
+    
+    /// Autocomplete the URL bar input from history
+    fn suggest_urls(&mut self, shared: &Shared) {
+        let input = self.chrome.url_input();
+        let completion = shared.history.inline_completion(input);
+        let suggestions = shared.history.suggest(input, 5).into_iter().map(|s| s.url).collect();
+        self.chrome.set_url_suggestions(completion, suggestions);
+    }
+    
     /// Navigate to a URL
     fn navigate_to(&mut self, url: &str) {
         // Normalize URL
@@ -748,61 +1629,173 @@ impl BrowserApp {
         self.request_redraw();
     }
     
-    fn request_redraw(&self) {
-        if let Some(window) = &self.window {
-            window.request_redraw();
+    /// Element a file drag at a window position is over. Drops anywhere
+    /// in the content area go to the document; the browser UI takes none.
+    fn drag_target(&self) -> impl Fn(f32, f32) -> Option<u64> {
+        let content_left = TAB_BAR_WIDTH as f32;
+        move |x, y| (x >= content_left && y >= 0.0).then_some(DOCUMENT_TARGET)
+    }
+    
+    /// Fire drag events at the page
+    fn dispatch_drag_events(&mut self, events: Vec<PageDragEvent>) {
+        for PageDragEvent { target, event } in events {
+            log::debug!(
+                "{:?} on {} (types: {:?}, files: {})",
+                event.event_type, target, event.data_transfer.types(), event.data_transfer.files.len(),
+            );
         }
     }
-}
-
-impl ApplicationHandler for BrowserApp {
-    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if self.window.is_some() {
-            return;
+    
+    /// Apply an input method update to the URL bar and fire its
+    /// composition events
+    fn apply_ime_update(&mut self, shared: &mut Shared, update: ImeUpdate) {
+        for event in &update.events {
+            log::debug!("{} (data: {:?})", event.event_type.as_str(), event.data);
         }
-        
-        // Create window
-        let attrs = Window::default_attributes()
-            .with_title("fOS Browser")
-            .with_inner_size(winit::dpi::LogicalSize::new(1024, 768));
-        
-        let window = Arc::new(event_loop.create_window(attrs).unwrap());
-        
-        // Create software rendering surface
-        let context = softbuffer::Context::new(window.clone()).unwrap();
-        let surface = softbuffer::Surface::new(&context, window.clone()).unwrap();
-        
-        self.window = Some(window);
-        self.surface = Some(surface);
-        
-        // Create initial tab
-        if !self.initial_url.is_empty() {
-            self.tabs.new_tab(&self.initial_url);
-        } else {
-            self.tabs.new_tab("about:blank");
+        self.chrome.set_composition(self.ime.composition().cloned());
+        if let Some(text) = update.commit {
+            self.chrome.insert_text(&text);
+            self.suggest_urls(shared);
+        }
+        if !update.events.is_empty() {
+            self.request_redraw();
+        }
+    }
+    
+    /// Keep the input method on only while the URL bar is edited, and
+    /// tell it where the caret is
+    fn sync_ime(&mut self, shared: &mut Shared) {
+        let update = self.ime.set_editable_focus(self.chrome.is_url_bar_focused());
+        self.apply_ime_update(shared, update);
+        let (x, y, width, height) = self.chrome.ime_area();
+        self.ime.report_cursor_area(x, y, width, height);
+    }
+    
+    /// Fire queued pointerlockchange/pointerlockerror events
+    fn dispatch_pointer_lock_events(&mut self) {
+        for event in self.pointer_lock.take_events() {
+            log::debug!("{} (locked: {})", event.as_str(), self.pointer_lock.is_locked());
+        }
+    }
+    
+    /// A click in the page: counts as a first-party interaction for storage
+    /// access and lets the page's scripts request it
+    fn notify_user_activation(&mut self, shared: &mut Shared) {
+        self.user_activation = Some(std::time::Instant::now());
+        if let Some(tab) = self.tabs.active_tab() {
+            shared.security.record_interaction(tab.id as u64, &self.current_url);
+        }
+        if let Some(runtime) = self.current_page.as_ref().and_then(|p| p.js_runtime.as_ref()) {
+            runtime.notify_user_activation();
+        }
+    }
+    
+    /// Follow a link or `window.open` from a frame of the shown page: in
+    /// the frame `target` names, or a new tab when no frame has that name.
+    /// Scripts only open tabs right after a click in the page.
+    fn open_target(&mut self, shared: &mut Shared, source: FrameId, url: &str, target: &str, script: bool) {
+        let Some(tab) = self.tabs.active_tab_mut() else { return };
+        let root = tab.frame_tree.root();
+        match tab.frame_tree.find_frame(source, target) {
+            Some(frame) if frame == root => {
+                log::info!("Navigating to: {}", url);
+                self.navigate_to(url);
+            }
+            Some(frame) => {
+                log::info!("Navigating frame {} to {}", frame.0, url);
+                tab.navigate_frame(frame, url, source);
+                self.reload_frames(shared);
+            }
+            None if script && self.user_activation.is_none_or(|t| t.elapsed() > POPUP_ACTIVATION) => {
+                log::info!("Blocked a window.open of {} without a click", url);
+            }
+            None => {
+                log::info!("Opening {} in a new tab", url);
+                self.user_activation = None;
+                self.tabs.new_tab(url);
+                self.needs_reload = true;
+                self.request_redraw();
+            }
+        }
+    }
+    
+    /// Reload the shown page's frames at their entries in the tab's
+    /// history, keeping the page and its scripts
+    fn reload_frames(&mut self, shared: &mut Shared) {
+        let (Some(tab), Some(page)) = (self.tabs.active_tab_mut(), self.current_page.as_mut()) else { return };
+        let url = page.url.clone();
+        self.frames.load(&mut tab.frame_tree, page, frame_fetcher(shared, &url));
+        let html = self.current_html.clone();
+        self.render_page(shared, &html, &url, false);
+        self.request_redraw();
+    }
+    
+    /// Go back or forward in the active tab's joint session history: to
+    /// another page, or to other documents in the shown page's frames
+    fn traverse_history(&mut self, shared: &mut Shared, back: bool) {
+        let Some(tab) = self.tabs.active_tab_mut() else { return };
+        match if back { tab.go_back() } else { tab.go_forward() } {
+            Some(Traversal::Page(_)) => self.needs_reload = true,
+            Some(Traversal::Frames) => self.reload_frames(shared),
+            None => {}
         }
-        
-        self.needs_reload = true;
         self.request_redraw();
     }
     
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+    fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+    
+    /// Handle an event for this window; closing is up to the app
+    fn handle_event(&mut self, shared: &mut Shared, event: WindowEvent) {
+        // The files of one drop arrive as consecutive events
+        if !matches!(event, WindowEvent::DroppedFile(_)) {
+            let events = self.drag_drop.finish_os_drop(self.drag_target());
+            self.dispatch_drag_events(events);
+        }
+        
         match event {
-            WindowEvent::CloseRequested => {
-                event_loop.exit();
-            }
             WindowEvent::RedrawRequested => {
-                self.render();
+                self.render(shared);
+            }
+            WindowEvent::HoveredFile(path) => {
+                self.drag_drop.os_file_hovered(path);
+                let events = self.drag_drop.os_drag_over(self.mouse_x as f32, self.mouse_y as f32, self.drag_target());
+                self.dispatch_drag_events(events);
+            }
+            WindowEvent::HoveredFileCancelled => {
+                let events = self.drag_drop.os_drag_cancelled();
+                self.dispatch_drag_events(events);
+            }
+            WindowEvent::DroppedFile(path) => {
+                self.drag_drop.os_file_dropped(path);
+                self.request_redraw();
             }
             WindowEvent::Resized(_) => {
                 self.request_redraw();
             }
+            WindowEvent::Focused(false) => {
+                self.pointer_lock.release();
+                self.dispatch_pointer_lock_events();
+            }
             WindowEvent::ModifiersChanged(new_modifiers) => {
                 self.modifiers = new_modifiers.state();
             }
             WindowEvent::KeyboardInput { event, .. } => {
                 let modifiers = self.modifiers;
-                self.handle_key(event, &modifiers);
+                self.handle_key(shared, event, &modifiers);
+            }
+            WindowEvent::Ime(ime) => {
+                let input = match ime {
+                    winit::event::Ime::Enabled => ImeInput::Enabled,
+                    winit::event::Ime::Preedit(text, cursor) => {
+                        ImeInput::Preedit(text, cursor.map(|(start, end)| start..end))
+                    }
+                    winit::event::Ime::Commit(text) => ImeInput::Commit(text),
+                    winit::event::Ime::Disabled => ImeInput::Disabled,
+                };
+                let update = self.ime.handle(input);
+                self.apply_ime_update(shared, update);
             }
             WindowEvent::MouseInput { state, button, .. } => {
                 if state == ElementState::Pressed && button == winit::event::MouseButton::Left {
@@ -816,6 +1809,8 @@ impl ApplicationHandler for BrowserApp {
                         let content_y = self.mouse_y;
                         
                         if content_x >= 0 && content_y >= 0 {
+                            self.notify_user_activation(shared);
+                            
                             // Links are stored in render buffer coordinates
                             // Display applies scroll_y shift (scroll_offset - render_start_y)
                             // To match, convert screen y to buffer y by adding the scroll shift
@@ -823,13 +1818,53 @@ impl ApplicationHandler for BrowserApp {
                             let hit_x = content_x as f32;
                             let hit_y = content_y as f32 + scroll_y;  // Screen to buffer coords
                             
-                            // Check link regions
-                            if let Some(ref rendered) = self.rendered_page {
+                            // A blocked frame's placeholder unblocks frames on
+                            // the site and reloads the page
+                            let blocked_frame = self.rendered_page.as_ref().and_then(|rendered| {
+                                rendered.blocked_frames.iter().find(|frame| {
+                                    hit_x >= frame.x && hit_x <= frame.x + frame.width &&
+                                    hit_y >= frame.y && hit_y <= frame.y + frame.height
+                                })
+                            });
+                            if let Some(frame) = blocked_frame {
+                                log::info!("Loading blocked frame {}", frame.url);
+                                let url = self.current_url.clone();
+                                shared.unblock_content(&url, ContentCategory::Frames);
+                                if let Some(tab) = self.tabs.active_tab_mut() {
+                                    tab.needs_network_load = true;
+                                }
+                                self.needs_reload = true;
+                            } else if let Some(ref rendered) = self.rendered_page {
                                 for link in &rendered.links {
                                     if hit_x >= link.x && hit_x <= link.x + link.width &&
                                        hit_y >= link.y && hit_y <= link.y + link.height {
                                         // Found a link click!
                                         let href = link.href.clone();
+                                        let target = link.target.clone();
+                                        
+                                        // Proceed past the certificate error page
+                                        // being shown; pages cannot link here
+                                        if href == CERT_ERROR_PROCEED {
+                                            if let Some((host, code)) = self.cert_error.take() {
+                                                if shared.allow_invalid_certificate(&host, code) {
+                                                    if let Some(tab) = self.tabs.active_tab_mut() {
+                                                        tab.needs_network_load = true;
+                                                    }
+                                                    self.needs_reload = true;
+                                                }
+                                            }
+                                            break;
+                                        }
+                                        
+                                        // Buttons on internal pages; other pages
+                                        // cannot press them
+                                        if let Some(action) = InternalAction::parse(&href) {
+                                            if self.current_url.starts_with("about:") {
+                                                shared.run_internal_action(&action);
+                                                self.navigate_to(action.page());
+                                            }
+                                            break;
+                                        }
                                         
                                         // Handle anchor links (in-page navigation)
                                         if href.starts_with("#") {
@@ -872,8 +1907,8 @@ impl ApplicationHandler for BrowserApp {
                                             }
                                         };
                                         
-                                        log::info!("Navigating to: {}", full_url);
-                                        self.navigate_to(&full_url);
+                                        let root = self.frames.tree().root();
+                                        self.open_target(shared, root, &full_url, &target, false);
                                         break;
                                     }
                                 }
@@ -884,9 +1919,21 @@ impl ApplicationHandler for BrowserApp {
                 }
             }
             WindowEvent::CursorMoved { position, .. } => {
+                let moved = self.pointer_lock.cursor_moved(position.x as f32, position.y as f32);
+                if self.pointer_lock.is_locked() {
+                    // The hidden cursor is held in place; the page only gets deltas
+                    if let Some(event) = moved {
+                        log::trace!("pointermove movementX={} movementY={}", event.movement_x, event.movement_y);
+                    }
+                    return;
+                }
                 self.mouse_x = position.x as i32;
                 self.mouse_y = position.y as i32;
                 self.chrome.handle_mouse_move(self.mouse_x, self.mouse_y);
+                if self.drag_drop.is_os_dragging() {
+                    let events = self.drag_drop.os_drag_over(position.x as f32, position.y as f32, self.drag_target());
+                    self.dispatch_drag_events(events);
+                }
             }
             WindowEvent::MouseWheel { delta, .. } => {
                 // Handle scroll with sliding window buffer
@@ -928,9 +1975,17 @@ impl ApplicationHandler for BrowserApp {
                         let url = self.current_url.clone();
                         let content_width = self.width.saturating_sub(TAB_BAR_WIDTH);
                         let render_height = (viewport_height * 5.0) as u32;
+                        let save_data = shared.save_data;
+                        let device_pixel_ratio = self.window.scale_factor() as f32;
+                        let text_scaling = self.renderer.text_scaling().clone();
+                        let frame_contents = self.frames.contents();
                         
                         std::thread::spawn(move || {
                             let mut renderer = PageRenderer::new(content_width, render_height);
+                            renderer.set_frame_contents(frame_contents);
+                            renderer.set_prefers_reduced_data(save_data);
+                            renderer.set_device_pixel_ratio(device_pixel_ratio);
+                            renderer.set_text_scaling(text_scaling);
                             if let Some(rendered) = renderer.render_html(&html, &url, new_start) {
                                 let _ = tx.send((rendered, new_start));
                             }
@@ -942,10 +1997,420 @@ impl ApplicationHandler for BrowserApp {
             }
             _ => {}
         }
+        
+        // Focus may have moved into or out of the URL bar
+        self.sync_ime(shared);
+    }
+}
+
+/// Fetch of the iframes of the page at `page_url`: their HTML, or None
+/// if they are blocked or fail to load
+fn frame_fetcher<'a>(shared: &'a mut Shared, page_url: &'a str) -> impl FnMut(&str, &str) -> Option<String> + 'a {
+    move |frame_url, referrer| {
+        if shared.security.content_blocking.should_block(frame_url, page_url, ContentCategory::Frames) {
+            return None;
+        }
+        let result = shared.network.fetch(frame_url, Some(referrer)).ok()?;
+        if !(200..300).contains(&result.status) {
+            return None;
+        }
+        String::from_utf8(result.body).ok()
+    }
+}
+
+/// Trace slice for a script task of the page at `url`
+fn script_task(task: ScriptEventType, url: &str) -> TraceKind {
+    TraceKind::Script {
+        task,
+        stack_top: Some(ScriptLocation {
+            function_name: String::new(),
+            url: url.to_string(),
+            line: 0,
+            column: 0,
+        }),
+    }
+}
+
+/// Browser app state for event loop
+struct BrowserApp {
+    /// Layers shared by all windows
+    shared: Shared,
+    /// Open windows
+    windows: HashMap<WindowId, BrowserWindow>,
+    /// Windows in the order they were opened
+    window_order: Vec<WindowId>,
+    /// Window with keyboard focus
+    focused: Option<WindowId>,
+    /// Initial URL
+    initial_url: String,
+    /// Session store, journaled as tabs change
+    session: Option<SessionStore>,
+    /// Session as last written to the store
+    saved_session: SessionState,
+    /// Session to reopen once the first window exists
+    restored_session: Option<SessionState>,
+    /// Last time session changes were journaled
+    last_session_check: std::time::Instant,
+}
+
+impl BrowserApp {
+    fn new(initial_url: String) -> Self {
+        Self {
+            shared: Shared::new(),
+            windows: HashMap::new(),
+            window_order: Vec::new(),
+            focused: None,
+            initial_url,
+            session: None,
+            saved_session: SessionState::default(),
+            restored_session: None,
+            last_session_check: std::time::Instant::now(),
+        }
+    }
+    
+    /// Apply the data saver preference to networking and every window
+    fn set_save_data(&mut self, enabled: bool) {
+        self.shared.save_data = enabled;
+        self.shared.network.set_save_data(enabled);
+        for window in self.windows.values_mut() {
+            window.set_save_data(enabled);
+        }
+    }
+    
+    /// Open a window showing `tabs`
+    fn open_window(&mut self, event_loop: &ActiveEventLoop, tabs: TabManager) -> Option<WindowId> {
+        let attrs = Window::default_attributes()
+            .with_title(WINDOW_TITLE)
+            .with_inner_size(winit::dpi::LogicalSize::new(1024, 768));
+        
+        let window = match event_loop.create_window(attrs) {
+            Ok(window) => Arc::new(window),
+            Err(e) => {
+                log::error!("Failed to open window: {}", e);
+                return None;
+            }
+        };
+        
+        // Create software rendering surface
+        let context = softbuffer::Context::new(window.clone()).unwrap();
+        let surface = softbuffer::Surface::new(&context, window.clone()).unwrap();
+        
+        let id = window.id();
+        let browser_window = BrowserWindow::new(window, surface, tabs, &self.shared);
+        browser_window.request_redraw();
+        self.windows.insert(id, browser_window);
+        self.window_order.push(id);
+        self.focused = Some(id);
+        Some(id)
+    }
+    
+    /// Close a window and its tabs; closing the last one quits
+    fn close_window(&mut self, event_loop: &ActiveEventLoop, id: WindowId) {
+        if self.window_order.len() <= 1 {
+            self.save_session(true);
+            event_loop.exit();
+            return;
+        }
+        
+        self.windows.remove(&id);
+        self.window_order.retain(|&w| w != id);
+        if self.focused == Some(id) {
+            self.focused = self.window_order.last().copied();
+        }
+    }
+    
+    /// Move a tab from one window to position `index` of another, where it
+    /// becomes the active tab; a window left without tabs closes
+    fn move_tab(&mut self, event_loop: &ActiveEventLoop, tab: TabId, from: WindowId, to: WindowId, index: usize) -> bool {
+        if from == to {
+            return self.windows.get_mut(&from).is_some_and(|w| w.tabs.move_tab(tab, index));
+        }
+        if !self.windows.contains_key(&to) {
+            return false;
+        }
+        let Some(source) = self.windows.get_mut(&from) else { return false };
+        let was_shown = source.tabs.is_active(tab);
+        let Some(moved) = source.tabs.detach_tab(tab) else { return false };
+        if was_shown {
+            source.drop_current_page();
+        }
+        source.request_redraw();
+        let emptied = source.tabs.count() == 0;
+        
+        let target = self.windows.get_mut(&to).unwrap();
+        target.tabs.attach_tab(moved, index, true);
+        target.needs_reload = true;
+        target.request_redraw();
+        
+        if emptied {
+            self.close_window(event_loop, from);
+        }
+        true
+    }
+    
+    /// Move a tab out of its window into a new one
+    fn move_tab_to_new_window(&mut self, event_loop: &ActiveEventLoop, tab: TabId, from: WindowId) {
+        // A window's only tab has nowhere better to go
+        if self.windows.get(&from).is_none_or(|w| w.tabs.count() < 2) {
+            return;
+        }
+        let tabs = TabManager::with_ids(self.shared.tab_ids.clone());
+        if let Some(to) = self.open_window(event_loop, tabs) {
+            self.move_tab(event_loop, tab, from, to, 0);
+        }
+    }
+    
+    /// Carry out what a window asked for while handling an event
+    fn run_window_commands(&mut self, event_loop: &ActiveEventLoop, id: WindowId) {
+        let Some(commands) = self.windows.get_mut(&id).map(|w| std::mem::take(&mut w.commands)) else {
+            return;
+        };
+        for command in commands {
+            match command {
+                WindowCommand::NewWindow => {
+                    let mut tabs = TabManager::with_ids(self.shared.tab_ids.clone());
+                    tabs.new_tab("about:blank");
+                    self.open_window(event_loop, tabs);
+                }
+                WindowCommand::MoveTabToNewWindow(tab) => self.move_tab_to_new_window(event_loop, tab, id),
+                WindowCommand::Close => self.close_window(event_loop, id),
+            }
+        }
+    }
+    
+    /// Current session: the tabs of all windows, in the order the windows
+    /// were opened, with the focused window's active tab
+    fn session_state(&self) -> SessionState {
+        let mut state = SessionState::default();
+        for id in &self.window_order {
+            let window_state = self.windows[id].session_state();
+            state.tabs.extend(window_state.tabs);
+            if self.focused == Some(*id) || state.active.is_none() {
+                state.active = window_state.active;
+            }
+        }
+        state
+    }
+    
+    /// Journal session changes, or write a snapshot when one is due or
+    /// forced
+    fn save_session(&mut self, force_snapshot: bool) {
+        if self.session.is_none() {
+            return;
+        }
+        let state = self.session_state();
+        let Some(store) = self.session.as_mut() else { return };
+        let result = if force_snapshot || store.snapshot_due() {
+            store.snapshot(&state)
+        } else {
+            store.record(&self.saved_session.diff(&state))
+        };
+        match result {
+            Ok(()) => self.saved_session = state,
+            Err(e) => log::warn!("Failed to save session to {}: {}", store.dir().display(), e),
+        }
+    }
+    
+    /// Run extension work queued by scripts: start background pages, carry
+    /// out tab changes in the focused window and deliver callbacks and
+    /// messages
+    #[cfg(feature = "extensions")]
+    fn pump_extensions(&mut self) {
+        let shared = &mut self.shared;
+        shared.extension_runtime.sync(&shared.extensions);
+        
+        if let Some(window) = self.focused.and_then(|id| self.windows.get_mut(&id)) {
+            let before = window.tabs.active_tab().map(|t| (t.id, t.needs_network_load));
+            let page = window.tabs.active_tab().map(|t| t.id)
+                .zip(window.current_page.as_ref().and_then(|p| p.js_runtime.as_ref()));
+            let handled = shared.extension_runtime.dispatch(&mut window.tabs, page);
+            
+            // A tab opened, switched to or navigated
+            if window.tabs.active_tab().map(|t| (t.id, t.needs_network_load)) != before {
+                window.needs_reload = true;
+            }
+            if handled > 0 {
+                window.request_redraw();
+            }
+        }
+        shared.extension_runtime.process_timers();
+    }
+    
+    /// Measure memory across windows and hibernate background tabs under
+    /// pressure or over the page memory limit
+    fn check_memory_pressure(&mut self) {
+        let memory = &mut self.shared.memory;
+        memory.mark_pressure_checked();
+        
+        let used = self.windows.values().map(BrowserWindow::memory_usage).sum();
+        let dom_nodes = self.windows.values().map(BrowserWindow::dom_nodes).sum();
+        memory.update_stats(used, dom_nodes, 0);
+        let under_pressure = memory.is_under_pressure();
+        if under_pressure {
+            memory.apply_pressure_response();
+        }
+        
+        let candidates = self.windows.values().flat_map(|w| w.tabs.hibernation_candidates()).collect();
+        let chosen = memory.select_tabs_to_hibernate(candidates);
+        if !under_pressure && chosen.is_empty() {
+            return;
+        }
+        for id in chosen {
+            let Some(tab) = self.windows.values_mut().find_map(|w| w.tabs.get_mut(id)) else { continue };
+            let scroll_y = tab.pending_restore.as_ref().map_or(0.0, |r| r.scroll.y as f64);
+            let freed = tab.hibernate();
+            memory.hibernate_tab(id as u64, &tab.url, &tab.title, scroll_y);
+            memory.release(freed);
+        }
+        log::info!("{}", memory.log_stats());
+    }
+    
+    /// Hold renderers to their memory ceilings: release the renderers of
+    /// closed tabs, then purge the caches of a renderer over its ceiling,
+    /// and discard its tabs if that was not enough
+    fn enforce_renderer_memory(&mut self) {
+        let processes = &mut self.shared.processes;
+        let usage: HashMap<TabId, usize> = self.windows.values()
+            .flat_map(|w| w.tabs.tabs_in_order())
+            .map(|t| (t.id, t.memory_usage()))
+            .collect();
+        let closed: Vec<_> = processes.renderer_tabs().filter(|t| !usage.contains_key(&t.0)).collect();
+        for tab in closed {
+            if let Err(e) = processes.close_tab(tab) {
+                log::warn!("Failed to stop the renderer of tab {}: {}", tab, e);
+            }
+        }
+        if let Some(shown) = self.focused.and_then(|id| self.windows.get(&id)).and_then(|w| w.tabs.active_tab()) {
+            processes.browser_mut().set_focused_tab(fos_engine::TabId::new(shown.id));
+        }
+        
+        // One report per renderer, of the memory of every tab it hosts
+        let mut reported = HashSet::new();
+        let tabs: Vec<_> = processes.renderer_tabs().collect();
+        for tab in tabs {
+            let Some(process) = processes.get_renderer(tab).map(|r| r.id()) else { continue };
+            if !reported.insert(process) {
+                continue;
+            }
+            let hosted = processes.hosted_tabs(process);
+            let bytes = hosted.iter().filter_map(|t| usage.get(&t.0)).sum();
+            match processes.report_renderer_memory(tab, bytes) {
+                Ok(OomAction::None) => {}
+                Ok(OomAction::PurgeCaches) => {
+                    let mut freed = 0;
+                    for window in self.windows.values_mut() {
+                        for t in &hosted {
+                            freed += window.tabs.get_mut(t.0).map_or(0, |tab| tab.purge_caches());
+                        }
+                    }
+                    log::info!("Renderer {} over its memory ceiling, purged {} bytes of caches", process, freed);
+                    self.shared.memory.release(freed);
+                }
+                Ok(OomAction::DiscardTab(victim)) => {
+                    let Some(window) = self.windows.values_mut().find(|w| w.tabs.get(victim.0).is_some()) else { continue };
+                    // A shown tab reloads at once, in a renderer of its own
+                    if window.tabs.is_active(victim.0) {
+                        window.drop_current_page();
+                        window.request_redraw();
+                    }
+                    let Some(tab) = window.tabs.get_mut(victim.0) else { continue };
+                    let scroll_y = tab.pending_restore.as_ref().map_or(0.0, |r| r.scroll.y as f64);
+                    let freed = tab.hibernate();
+                    self.shared.memory.hibernate_tab(victim.0 as u64, &tab.url, &tab.title, scroll_y);
+                    self.shared.memory.release(freed);
+                    log::info!("Renderer {} over its memory ceiling, discarded tab {}", process, victim);
+                }
+                Err(e) => log::warn!("Failed to discard a tab of renderer {}: {}", process, e),
+            }
+        }
+    }
+}
+
+impl ApplicationHandler for BrowserApp {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if !self.windows.is_empty() {
+            return;
+        }
+        
+        // Reopen the last session, then the initial URL
+        let mut tabs = TabManager::with_ids(self.shared.tab_ids.clone());
+        let restored = match self.restored_session.take() {
+            Some(session) => {
+                tabs.restore_session(&session);
+                tabs.count() > 0
+            }
+            None => false,
+        };
+        if !self.initial_url.is_empty() && !(restored && self.initial_url == "about:blank") {
+            tabs.new_tab(&self.initial_url);
+        } else if !restored {
+            tabs.new_tab("about:blank");
+        }
+        
+        if self.open_window(event_loop, tabs).is_none() {
+            event_loop.exit();
+            return;
+        }
+        
+        // Restored tabs have new IDs, so start the store afresh
+        self.save_session(true);
+    }
+    
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => {
+                self.close_window(event_loop, id);
+                return;
+            }
+            WindowEvent::Focused(true) => {
+                self.focused = Some(id);
+            }
+            _ => {}
+        }
+        
+        let Some(window) = self.windows.get_mut(&id) else { return };
+        window.handle_event(&mut self.shared, event);
+        self.run_window_commands(event_loop, id);
+    }
+    
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _id: DeviceId, event: DeviceEvent) {
+        // Raw motion goes to the focused window, whose pointer may be locked
+        let Some(window) = self.focused.and_then(|id| self.windows.get_mut(&id)) else { return };
+        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
+            if let Some(event) = window.pointer_lock.raw_motion(dx, dy) {
+                log::trace!("pointermove movementX={} movementY={}", event.movement_x, event.movement_y);
+            }
+        }
     }
     
     fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
-        // Process JavaScript timers during idle time
-        self.process_js_timers();
+        for window in self.windows.values_mut() {
+            // Content scripts at document idle, the first idle time after load
+            #[cfg(feature = "extensions")]
+            if std::mem::take(&mut window.document_idle_pending) {
+                window.inject_content_scripts(&self.shared, RunAt::DocumentIdle);
+            }
+            
+            // Process JavaScript timers during idle time
+            window.process_js_timers(&mut self.shared);
+        }
+        
+        // Extension messages, callbacks and tab changes
+        #[cfg(feature = "extensions")]
+        self.pump_extensions();
+        
+        // Hibernate background tabs when memory runs short
+        if self.shared.memory.should_check_pressure() {
+            self.check_memory_pressure();
+        }
+        
+        // Journal session changes and purge bounce trackers at most once a
+        // second
+        if self.last_session_check.elapsed() >= std::time::Duration::from_secs(1) {
+            self.last_session_check = std::time::Instant::now();
+            self.save_session(false);
+            self.shared.security.purge_bounce_trackers();
+            self.enforce_renderer_memory();
+        }
     }
 }
@@ -1,8 +1,16 @@
 //! Form Autofill
 //!
 //! Profile-based autofill for addresses, names, and credit cards.
+//! Profiles are encrypted at rest, and forms are filled in two phases: a
+//! preview the user sees, then a commit that writes the values.
 
 use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
 
 /// Autofill field type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -64,59 +72,66 @@ pub enum AutofillFieldType {
     Photo,
 }
 
+/// Autocomplete tokens of each field type
+const FIELD_TOKENS: &[(&str, AutofillFieldType)] = &[
+    ("name", AutofillFieldType::Name),
+    ("given-name", AutofillFieldType::GivenName),
+    ("additional-name", AutofillFieldType::AdditionalName),
+    ("family-name", AutofillFieldType::FamilyName),
+    ("honorific-prefix", AutofillFieldType::HonorificPrefix),
+    ("honorific-suffix", AutofillFieldType::HonorificSuffix),
+    ("nickname", AutofillFieldType::Nickname),
+    ("email", AutofillFieldType::Email),
+    ("tel", AutofillFieldType::Tel),
+    ("tel-country-code", AutofillFieldType::TelCountryCode),
+    ("tel-national", AutofillFieldType::TelNational),
+    ("tel-area-code", AutofillFieldType::TelAreaCode),
+    ("tel-local", AutofillFieldType::TelLocal),
+    ("street-address", AutofillFieldType::StreetAddress),
+    ("address-line1", AutofillFieldType::AddressLine1),
+    ("address-line2", AutofillFieldType::AddressLine2),
+    ("address-line3", AutofillFieldType::AddressLine3),
+    ("address-level1", AutofillFieldType::AddressLevel1),
+    ("address-level2", AutofillFieldType::AddressLevel2),
+    ("address-level3", AutofillFieldType::AddressLevel3),
+    ("address-level4", AutofillFieldType::AddressLevel4),
+    ("country", AutofillFieldType::Country),
+    ("country-name", AutofillFieldType::CountryName),
+    ("postal-code", AutofillFieldType::PostalCode),
+    ("cc-name", AutofillFieldType::CcName),
+    ("cc-given-name", AutofillFieldType::CcGivenName),
+    ("cc-family-name", AutofillFieldType::CcFamilyName),
+    ("cc-number", AutofillFieldType::CcNumber),
+    ("cc-exp", AutofillFieldType::CcExp),
+    ("cc-exp-month", AutofillFieldType::CcExpMonth),
+    ("cc-exp-year", AutofillFieldType::CcExpYear),
+    ("cc-csc", AutofillFieldType::CcCsc),
+    ("cc-type", AutofillFieldType::CcType),
+    ("organization", AutofillFieldType::Organization),
+    ("organization-title", AutofillFieldType::OrganizationTitle),
+    ("username", AutofillFieldType::Username),
+    ("new-password", AutofillFieldType::NewPassword),
+    ("current-password", AutofillFieldType::CurrentPassword),
+    ("one-time-code", AutofillFieldType::OneTimeCode),
+    ("bday", AutofillFieldType::Bday),
+    ("bday-day", AutofillFieldType::BdayDay),
+    ("bday-month", AutofillFieldType::BdayMonth),
+    ("bday-year", AutofillFieldType::BdayYear),
+    ("sex", AutofillFieldType::Sex),
+    ("url", AutofillFieldType::Url),
+    ("photo", AutofillFieldType::Photo),
+];
+
 impl AutofillFieldType {
     /// Parse from autocomplete attribute value
     pub fn parse(value: &str) -> Option<Self> {
         let value = value.trim().to_lowercase();
-        Some(match value.as_str() {
-            "name" => Self::Name,
-            "given-name" => Self::GivenName,
-            "additional-name" => Self::AdditionalName,
-            "family-name" => Self::FamilyName,
-            "honorific-prefix" => Self::HonorificPrefix,
-            "honorific-suffix" => Self::HonorificSuffix,
-            "nickname" => Self::Nickname,
-            "email" => Self::Email,
-            "tel" => Self::Tel,
-            "tel-country-code" => Self::TelCountryCode,
-            "tel-national" => Self::TelNational,
-            "tel-area-code" => Self::TelAreaCode,
-            "tel-local" => Self::TelLocal,
-            "street-address" => Self::StreetAddress,
-            "address-line1" => Self::AddressLine1,
-            "address-line2" => Self::AddressLine2,
-            "address-line3" => Self::AddressLine3,
-            "address-level1" => Self::AddressLevel1,
-            "address-level2" => Self::AddressLevel2,
-            "address-level3" => Self::AddressLevel3,
-            "address-level4" => Self::AddressLevel4,
-            "country" => Self::Country,
-            "country-name" => Self::CountryName,
-            "postal-code" => Self::PostalCode,
-            "cc-name" => Self::CcName,
-            "cc-given-name" => Self::CcGivenName,
-            "cc-family-name" => Self::CcFamilyName,
-            "cc-number" => Self::CcNumber,
-            "cc-exp" => Self::CcExp,
-            "cc-exp-month" => Self::CcExpMonth,
-            "cc-exp-year" => Self::CcExpYear,
-            "cc-csc" => Self::CcCsc,
-            "cc-type" => Self::CcType,
-            "organization" => Self::Organization,
-            "organization-title" => Self::OrganizationTitle,
-            "username" => Self::Username,
-            "new-password" => Self::NewPassword,
-            "current-password" => Self::CurrentPassword,
-            "one-time-code" => Self::OneTimeCode,
-            "bday" => Self::Bday,
-            "bday-day" => Self::BdayDay,
-            "bday-month" => Self::BdayMonth,
-            "bday-year" => Self::BdayYear,
-            "sex" => Self::Sex,
-            "url" => Self::Url,
-            "photo" => Self::Photo,
-            _ => return None,
-        })
+        FIELD_TOKENS.iter().find(|(token, _)| *token == value).map(|&(_, field)| field)
+    }
+    
+    /// Autocomplete token for this field type
+    pub fn as_str(&self) -> &'static str {
+        FIELD_TOKENS.iter().find(|(_, field)| field == self).map_or("", |&(token, _)| token)
     }
     
     /// Check if field contains sensitive data
@@ -161,11 +176,166 @@ impl AutofillProfile {
         self.fields.get(&field).map(|s| s.as_str())
     }
     
+    /// Value to fill into a field of this type, derived from related fields
+    /// when the profile doesn't store it directly
+    pub fn value_for(&self, field: AutofillFieldType) -> Option<String> {
+        use AutofillFieldType as F;
+        if let Some(value) = self.get(field).filter(|v| !v.is_empty()) {
+            return Some(value.to_string());
+        }
+        
+        let name_parts: Vec<&str> = self.get(F::Name).map(|n| n.split_whitespace().collect()).unwrap_or_default();
+        let street_lines: Vec<&str> = self.get(F::StreetAddress)
+            .map(|s| s.lines().map(str::trim).filter(|l| !l.is_empty()).collect())
+            .unwrap_or_default();
+        let join = |fields: &[F], separator: &str| {
+            let parts: Vec<&str> = fields.iter().filter_map(|&f| self.get(f)).filter(|v| !v.is_empty()).collect();
+            (!parts.is_empty()).then(|| parts.join(separator))
+        };
+        
+        match field {
+            F::Name => join(&[F::GivenName, F::AdditionalName, F::FamilyName], " "),
+            F::GivenName | F::CcGivenName if name_parts.len() > 1 => Some(name_parts[0].to_string()),
+            F::FamilyName | F::CcFamilyName if name_parts.len() > 1 => Some(name_parts[name_parts.len() - 1].to_string()),
+            F::AdditionalName if name_parts.len() > 2 => Some(name_parts[1..name_parts.len() - 1].join(" ")),
+            F::CcName => self.value_for(F::Name),
+            F::StreetAddress => join(&[F::AddressLine1, F::AddressLine2, F::AddressLine3], "\n"),
+            F::AddressLine1 => street_lines.first().map(|l| l.to_string()),
+            F::AddressLine2 => street_lines.get(1).map(|l| l.to_string()),
+            F::AddressLine3 if street_lines.len() > 2 => Some(street_lines[2..].join(", ")),
+            _ => None,
+        }
+    }
+    
     /// Record usage
     pub fn mark_used(&mut self) {
         self.last_used = current_time_ms();
         self.use_count += 1;
     }
+    
+    /// One line of the profile store
+    fn serialize(&self) -> String {
+        let mut line = format!("{}\t{}\t{}\t{}\t{}", self.id, escape(&self.name), self.created, self.last_used, self.use_count);
+        for (field, value) in &self.fields {
+            line.push_str(&format!("\t{}={}", field.as_str(), escape(value)));
+        }
+        line
+    }
+    
+    fn deserialize(line: &str) -> Option<Self> {
+        let mut parts = line.split('\t');
+        let mut profile = Self {
+            id: parts.next()?.parse().ok()?,
+            name: unescape(parts.next()?),
+            created: parts.next()?.parse().ok()?,
+            last_used: parts.next()?.parse().ok()?,
+            use_count: parts.next()?.parse().ok()?,
+            fields: HashMap::new(),
+        };
+        for part in parts {
+            let (field, value) = part.split_once('=')?;
+            profile.fields.insert(AutofillFieldType::parse(field)?, unescape(value));
+        }
+        Some(profile)
+    }
+}
+
+/// Escape tabs and newlines so a value fits in one store field
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+    result
+}
+
+/// Key the profile store is encrypted with (ChaCha20-Poly1305)
+#[derive(Clone)]
+pub struct AutofillKey([u8; 32]);
+
+impl std::fmt::Debug for AutofillKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AutofillKey(..)")
+    }
+}
+
+impl AutofillKey {
+    /// A new random key
+    pub fn generate() -> Self {
+        let mut key = [0; 32];
+        SystemRandom::new().fill(&mut key).expect("system random source");
+        Self(key)
+    }
+    
+    pub fn from_bytes(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+    
+    /// Load the key kept at `path`, creating it on first use. The file is
+    /// only readable by the user.
+    pub fn load_or_create(path: &Path) -> io::Result<Self> {
+        if let Ok(bytes) = fs::read(path) {
+            if let Ok(key) = <[u8; 32]>::try_from(bytes.as_slice()) {
+                return Ok(Self(key));
+            }
+            log::warn!("Autofill key at {} is corrupt, replacing it", path.display());
+        }
+        
+        let key = Self::generate();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        io::Write::write_all(&mut options.open(path)?, &key.0)?;
+        Ok(key)
+    }
+    
+    fn aead_key(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &self.0).expect("32-byte key"))
+    }
+    
+    /// Encrypt to a random nonce followed by the ciphertext and tag
+    fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).expect("system random source");
+        let mut sealed = plaintext.to_vec();
+        self.aead_key()
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut sealed)
+            .expect("autofill store fits in one message");
+        let mut out = nonce.to_vec();
+        out.append(&mut sealed);
+        out
+    }
+    
+    /// Decrypt what [`seal`](Self::seal) produced; `None` if the data was
+    /// altered or sealed with another key
+    fn open(&self, sealed: &[u8]) -> Option<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let mut buffer = ciphertext.to_vec();
+        let plaintext = self.aead_key().open_in_place(nonce, Aad::empty(), &mut buffer).ok()?;
+        Some(plaintext.to_vec())
+    }
 }
 
 /// Credit card profile (stored separately with encryption)
@@ -224,6 +394,23 @@ pub struct FormField {
     pub label: Option<String>,
 }
 
+/// Values a profile would put in a form, shown to the user before they
+/// reach the page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutofillPreview {
+    pub profile_id: u64,
+    /// Value for each field, by element
+    pub values: HashMap<u64, String>,
+    /// Detected type of each filled field
+    pub field_types: HashMap<u64, AutofillFieldType>,
+}
+
+impl AutofillPreview {
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
 /// Autofill manager
 #[derive(Debug, Default)]
 pub struct AutofillManager {
@@ -232,6 +419,8 @@ pub struct AutofillManager {
     next_profile_id: u64,
     enabled: bool,
     save_passwords: bool,
+    /// Encrypted profile store
+    storage: Option<(PathBuf, AutofillKey)>,
 }
 
 impl AutofillManager {
@@ -242,9 +431,18 @@ impl AutofillManager {
             next_profile_id: 1,
             enabled: true,
             save_passwords: true,
+            storage: None,
         }
     }
     
+    /// Create with profiles stored at `path`, encrypted with `key`
+    pub fn with_storage(path: PathBuf, key: AutofillKey) -> Self {
+        let mut manager = Self::new();
+        manager.storage = Some((path, key));
+        manager.load();
+        manager
+    }
+    
     /// Enable/disable autofill
     pub fn set_enabled(&mut self, enabled: bool) {
         self.enabled = enabled;
@@ -255,6 +453,7 @@ impl AutofillManager {
         let id = self.next_profile_id;
         self.next_profile_id += 1;
         self.profiles.push(AutofillProfile { id, ..profile });
+        self.save();
         id
     }
     
@@ -263,22 +462,63 @@ impl AutofillManager {
         &self.profiles
     }
     
+    /// Change a stored profile
+    pub fn update_profile(&mut self, id: u64, update: impl FnOnce(&mut AutofillProfile)) -> bool {
+        let Some(profile) = self.profiles.iter_mut().find(|p| p.id == id) else { return false };
+        update(profile);
+        self.save();
+        true
+    }
+    
     /// Delete a profile
     pub fn delete_profile(&mut self, id: u64) {
         self.profiles.retain(|p| p.id != id);
+        self.save();
+    }
+    
+    /// Encrypt the profiles to disk
+    pub fn save(&self) {
+        let Some((path, key)) = &self.storage else { return };
+        
+        let data: String = self.profiles.iter().map(|p| p.serialize() + "\n").collect();
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = fs::write(path, key.seal(data.as_bytes())) {
+            log::warn!("Failed to save autofill profiles to {}: {}", path.display(), e);
+        }
+    }
+    
+    /// Decrypt the profiles from disk
+    pub fn load(&mut self) {
+        let Some((path, key)) = &self.storage else { return };
+        let Ok(sealed) = fs::read(path) else { return };
+        
+        let Some(data) = key.open(&sealed).and_then(|d| String::from_utf8(d).ok()) else {
+            log::warn!("Autofill profiles at {} could not be decrypted", path.display());
+            return;
+        };
+        self.profiles = data.lines().filter_map(AutofillProfile::deserialize).collect();
+        self.next_profile_id = self.profiles.iter().map(|p| p.id + 1).max().unwrap_or(1);
     }
     
     /// Detect field type from attributes
     pub fn detect_field_type(&self, field: &FormField) -> Option<AutofillFieldType> {
-        // First check explicit autocomplete attribute
+        // First check explicit autocomplete attribute, whose field name may
+        // follow section and shipping/billing tokens
         if let Some(ref autocomplete) = field.autocomplete {
-            if autocomplete != "off" && autocomplete != "on" {
-                if let Some(field_type) = AutofillFieldType::parse(autocomplete) {
-                    return Some(field_type);
-                }
+            if let Some(field_type) = autocomplete.split_whitespace().rev().find_map(AutofillFieldType::parse) {
+                return Some(field_type);
             }
         }
         
+        // Input types that say what the field holds
+        match field.field_type.as_deref().map(str::to_ascii_lowercase).as_deref() {
+            Some("email") => return Some(AutofillFieldType::Email),
+            Some("tel") => return Some(AutofillFieldType::Tel),
+            _ => {}
+        }
+        
         // Heuristic detection based on name/id/label
         let indicators = [
             field.name.as_deref(),
@@ -296,6 +536,9 @@ impl AutofillManager {
             if lower.contains("phone") || lower.contains("tel") || lower.contains("mobile") {
                 return Some(AutofillFieldType::Tel);
             }
+            if lower.contains("company") || lower.contains("organi") {
+                return Some(AutofillFieldType::Organization);
+            }
             if lower.contains("first") && lower.contains("name") {
                 return Some(AutofillFieldType::GivenName);
             }
@@ -305,6 +548,12 @@ impl AutofillManager {
             if lower.contains("name") && !lower.contains("user") {
                 return Some(AutofillFieldType::Name);
             }
+            if ["address2", "address_2", "address-2", "line2", "apartment", "suite", "apt"].iter().any(|k| lower.contains(k)) {
+                return Some(AutofillFieldType::AddressLine2);
+            }
+            if ["address1", "address_1", "address-1", "line1"].iter().any(|k| lower.contains(k)) {
+                return Some(AutofillFieldType::AddressLine1);
+            }
             if lower.contains("address") || lower.contains("street") {
                 return Some(AutofillFieldType::StreetAddress);
             }
@@ -361,28 +610,44 @@ impl AutofillManager {
             .collect()
     }
     
-    /// Fill form fields with profile data
-    pub fn fill_form(&mut self, profile_id: u64, fields: &[FormField]) -> HashMap<u64, String> {
-        let mut filled = HashMap::new();
-        
-        // First detect all field types
-        let field_types: Vec<_> = fields.iter()
-            .map(|f| (f.element_id, self.detect_field_type(f)))
-            .collect();
+    /// Values `profile_id` would fill into `fields`, matched by field
+    /// type; nothing is recorded until the preview is committed
+    pub fn preview(&self, profile_id: u64, fields: &[FormField]) -> Option<AutofillPreview> {
+        if !self.enabled {
+            return None;
+        }
+        let profile = self.profiles.iter().find(|p| p.id == profile_id)?;
         
-        if let Some(profile) = self.profiles.iter_mut().find(|p| p.id == profile_id) {
-            profile.mark_used();
-            
-            for (element_id, field_type) in field_types {
-                if let Some(ft) = field_type {
-                    if let Some(value) = profile.get(ft) {
-                        filled.insert(element_id, value.to_string());
-                    }
-                }
+        let mut preview = AutofillPreview { profile_id, values: HashMap::new(), field_types: HashMap::new() };
+        for field in fields {
+            // Passwords and card details come from their own stores
+            let Some(field_type) = self.detect_field_type(field).filter(|t| !t.is_sensitive()) else { continue };
+            if let Some(value) = profile.value_for(field_type) {
+                preview.values.insert(field.element_id, value);
+                preview.field_types.insert(field.element_id, field_type);
             }
         }
-        
-        filled
+        Some(preview)
+    }
+    
+    /// Accept a preview: the profile is marked used and its values are
+    /// returned to be written into the form
+    pub fn commit(&mut self, preview: AutofillPreview) -> HashMap<u64, String> {
+        if preview.is_empty() {
+            return HashMap::new();
+        }
+        if !self.update_profile(preview.profile_id, AutofillProfile::mark_used) {
+            return HashMap::new();
+        }
+        preview.values
+    }
+    
+    /// Fill form fields with profile data
+    pub fn fill_form(&mut self, profile_id: u64, fields: &[FormField]) -> HashMap<u64, String> {
+        match self.preview(profile_id, fields) {
+            Some(preview) => self.commit(preview),
+            None => HashMap::new(),
+        }
     }
 }
 
@@ -434,5 +699,65 @@ mod tests {
         };
         
         assert_eq!(manager.detect_field_type(&field), Some(AutofillFieldType::Email));
+        
+        let field = FormField {
+            autocomplete: Some("section-ship shipping address-line2".to_string()),
+            ..field
+        };
+        assert_eq!(manager.detect_field_type(&field), Some(AutofillFieldType::AddressLine2));
+    }
+    
+    fn field(element_id: u64, name: &str) -> FormField {
+        FormField {
+            element_id,
+            name: Some(name.to_string()),
+            id: None,
+            autocomplete: None,
+            field_type: None,
+            placeholder: None,
+            label: None,
+        }
+    }
+    
+    #[test]
+    fn test_preview_and_commit() {
+        let mut manager = AutofillManager::new();
+        let mut profile = AutofillProfile::new(0, "Home");
+        profile.set(AutofillFieldType::Name, "Ada King Lovelace");
+        profile.set(AutofillFieldType::StreetAddress, "12 St James's Square\nFlat 3");
+        let id = manager.add_profile(profile);
+        
+        let fields = [field(1, "first_name"), field(2, "last_name"), field(3, "address2"), field(4, "cvv")];
+        let preview = manager.preview(id, &fields).unwrap();
+        assert_eq!(preview.values[&1], "Ada");
+        assert_eq!(preview.values[&2], "Lovelace");
+        assert_eq!(preview.values[&3], "Flat 3");
+        assert!(!preview.values.contains_key(&4));
+        assert_eq!(manager.get_profiles()[0].use_count, 0);
+        
+        assert_eq!(manager.commit(preview).len(), 3);
+        assert_eq!(manager.get_profiles()[0].use_count, 1);
+    }
+    
+    #[test]
+    fn test_encrypted_storage() {
+        let path = std::env::temp_dir().join(format!("fos-autofill-test-{}", std::process::id()));
+        let key = AutofillKey::generate();
+        let mut manager = AutofillManager::with_storage(path.clone(), key.clone());
+        let mut profile = AutofillProfile::new(0, "Work");
+        profile.set(AutofillFieldType::Email, "ada@example.com");
+        profile.set(AutofillFieldType::StreetAddress, "1 Main St\n\tRear");
+        manager.add_profile(profile);
+        
+        let stored = fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("ada@example.com"));
+        
+        let loaded = AutofillManager::with_storage(path.clone(), key);
+        assert_eq!(loaded.get_profiles()[0].get(AutofillFieldType::Email), Some("ada@example.com"));
+        assert_eq!(loaded.get_profiles()[0].get(AutofillFieldType::StreetAddress), Some("1 Main St\n\tRear"));
+        
+        let other_key = AutofillManager::with_storage(path.clone(), AutofillKey::generate());
+        assert!(other_key.get_profiles().is_empty());
+        let _ = fs::remove_file(path);
     }
 }
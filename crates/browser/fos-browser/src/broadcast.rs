@@ -1,7 +1,10 @@
 //! Broadcast Channel API
 //!
-//! Cross-tab communication.
+//! Cross-tab communication. Channels are scoped by storage key as well as
+//! name, so a frame only hears channels opened by documents of its origin
+//! under the same top-level site.
 
+use fos_security::StorageKey;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
@@ -19,14 +22,17 @@ pub struct BroadcastMessage {
 pub struct BroadcastChannel {
     pub name: String,
     pub id: u64,
+    /// Storage key of the document that opened it
+    pub key: StorageKey,
     closed: bool,
 }
 
 impl BroadcastChannel {
-    pub fn new(name: &str, id: u64) -> Self {
+    pub fn new(name: &str, id: u64, key: StorageKey) -> Self {
         Self {
             name: name.to_string(),
             id,
+            key,
             closed: false,
         }
     }
@@ -43,7 +49,7 @@ impl BroadcastChannel {
 /// Broadcast channel manager
 #[derive(Debug, Default)]
 pub struct BroadcastChannelManager {
-    channels: HashMap<String, Vec<u64>>,
+    channels: HashMap<(StorageKey, String), Vec<u64>>,
     channel_instances: HashMap<u64, BroadcastChannel>,
     pending_messages: Vec<(u64, BroadcastMessage)>,
     next_id: u64,
@@ -54,16 +60,21 @@ impl BroadcastChannelManager {
         Self::default()
     }
     
-    /// Create a broadcast channel
-    pub fn create(&mut self, name: &str) -> u64 {
+    /// Create a broadcast channel for a document with storage key `key`
+    pub fn create(&mut self, name: &str, key: &StorageKey) -> u64 {
         let id = self.next_id;
         self.next_id += 1;
         
-        let channel = BroadcastChannel::new(name, id);
+        let channel = BroadcastChannel::new(name, id, key.clone());
         self.channel_instances.insert(id, channel);
         
+        // Opaque keys get no subscribers, not even each other
+        if key.is_opaque() {
+            return id;
+        }
+        
         self.channels
-            .entry(name.to_string())
+            .entry((key.clone(), name.to_string()))
             .or_default()
             .push(id);
         
@@ -81,6 +92,7 @@ impl BroadcastChannelManager {
         }
         
         let channel_name = channel.name.clone();
+        let partition = (channel.key.clone(), channel_name.clone());
         
         let message = BroadcastMessage {
             channel: channel_name.clone(),
@@ -89,8 +101,8 @@ impl BroadcastChannelManager {
             timestamp: Self::now(),
         };
         
-        // Send to all other channels with same name
-        if let Some(subscribers) = self.channels.get(&channel_name) {
+        // Send to all other channels with same name and storage key
+        if let Some(subscribers) = self.channels.get(&partition) {
             for &sub_id in subscribers {
                 if sub_id != channel_id {
                     if let Some(sub) = self.channel_instances.get(&sub_id) {
@@ -119,10 +131,10 @@ impl BroadcastChannelManager {
     /// Close a channel
     pub fn close(&mut self, channel_id: u64) {
         if let Some(channel) = self.channel_instances.get_mut(&channel_id) {
-            let name = channel.name.clone();
+            let partition = (channel.key.clone(), channel.name.clone());
             channel.close();
             
-            if let Some(subs) = self.channels.get_mut(&name) {
+            if let Some(subs) = self.channels.get_mut(&partition) {
                 subs.retain(|&id| id != channel_id);
             }
         }
@@ -144,8 +156,9 @@ mod tests {
     fn test_broadcast() {
         let mut mgr = BroadcastChannelManager::new();
         
-        let ch1 = mgr.create("test");
-        let ch2 = mgr.create("test");
+        let key = StorageKey::first_party("https://example.com/");
+        let ch1 = mgr.create("test", &key);
+        let ch2 = mgr.create("test", &key);
         
         mgr.post_message(ch1, "hello", "https://example.com");
         
@@ -153,4 +166,21 @@ mod tests {
         assert_eq!(messages.len(), 1);
         assert_eq!(messages[0].data, "hello");
     }
+    
+    #[test]
+    fn test_partitioned_channels() {
+        let mut mgr = BroadcastChannelManager::new();
+        
+        // One tracker embedded in two sites opens the same channel
+        let on_a = mgr.create("sync", &StorageKey::new("https://a.com/", "https://tracker.net/"));
+        let on_b = mgr.create("sync", &StorageKey::new("https://b.com/", "https://tracker.net/"));
+        mgr.post_message(on_a, "visitor=7", "https://tracker.net");
+        assert!(mgr.get_messages(on_b).is_empty());
+        
+        let opaque = StorageKey::new("https://a.com/", "data:text/html,x");
+        let first = mgr.create("sync", &opaque);
+        let second = mgr.create("sync", &opaque);
+        mgr.post_message(first, "hi", "null");
+        assert!(mgr.get_messages(second).is_empty());
+    }
 }
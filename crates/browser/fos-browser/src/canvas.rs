@@ -1,13 +1,20 @@
 //! Canvas Integration
 //!
 //! Integrates fos-canvas for <canvas> element support.
+//! Canvases follow their page's fingerprinting resistance: text sees only
+//! standard font families, and pixels read back get faint noise.
 
 use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
 use fos_dom::{Document, DomTree, NodeId};
 use fos_canvas::{
-    CanvasRenderingContext2D, Color,
-    FillStyle, StrokeStyle,
+    CanvasError, CanvasFonts, CanvasRenderingContext2D, Color,
+    FillStyle, StrokeStyle, OffscreenCanvas, PlaceholderCanvas, WebGLRenderingContext,
 };
+use fos_media::{CanvasCaptureError, CanvasCaptureTrack, CapturedFrame, MediaStream};
+use fos_security::FingerprintingProtection;
 
 /// Canvas manager for the browser
 pub struct CanvasManager {
@@ -15,6 +22,15 @@ pub struct CanvasManager {
     canvases: HashMap<u64, CanvasInstance>,
     /// Next canvas ID
     next_id: u64,
+    /// `captureStream()` tracks by canvas ID, with a hash of the pixels
+    /// last seen to detect changes
+    captures: HashMap<u64, (Vec<CanvasCaptureTrack>, u64)>,
+    /// URL of the page the canvases belong to
+    page_url: String,
+    /// Fingerprinting resistance of the browser, applied for the page
+    fingerprinting: FingerprintingProtection,
+    /// Fonts for canvas text when resistance hides some families
+    fonts: Option<Arc<CanvasFonts>>,
 }
 
 /// Canvas element instance
@@ -24,6 +40,10 @@ pub struct CanvasInstance {
     pub context: CanvasRenderingContext2D,
     pub bounds: CanvasBounds,
     pub context_type: CanvasContextType,
+    /// Set once control has been transferred to an OffscreenCanvas
+    pub placeholder: Option<PlaceholderCanvas>,
+    /// WebGL context, composited into `context` each frame
+    pub webgl: Option<WebGLRenderingContext>,
 }
 
 /// Canvas bounds for rendering
@@ -56,6 +76,26 @@ impl CanvasManager {
         Self {
             canvases: HashMap::new(),
             next_id: 1,
+            captures: HashMap::new(),
+            page_url: String::new(),
+            fingerprinting: FingerprintingProtection::new(),
+            fonts: None,
+        }
+    }
+    
+    /// Follow the fingerprinting resistance of the page at `page_url` for
+    /// its canvases' text and read-back
+    pub fn set_page(&mut self, page_url: &str, fingerprinting: FingerprintingProtection) {
+        self.fonts = fingerprinting.applies_to(page_url).then(|| {
+            let system = CanvasFonts::system();
+            let installed: Vec<String> = system.database().families().map(String::from).collect();
+            let visible = fingerprinting.visible_fonts(page_url, &installed).into_iter().map(String::from).collect();
+            Arc::new(system.with_families(visible))
+        });
+        self.page_url = page_url.to_string();
+        self.fingerprinting = fingerprinting;
+        for canvas in self.canvases.values_mut() {
+            canvas.context.set_fonts(self.fonts.clone().unwrap_or_else(CanvasFonts::system));
         }
     }
     
@@ -101,7 +141,10 @@ impl CanvasManager {
                     self.next_id += 1;
                     
                     // Create 2D context
-                    let context = CanvasRenderingContext2D::new(width, height);
+                    let mut context = CanvasRenderingContext2D::new(width, height);
+                    if let Some(fonts) = &self.fonts {
+                        context.set_fonts(fonts.clone());
+                    }
                     
                     self.canvases.insert(id, CanvasInstance {
                         id,
@@ -113,6 +156,8 @@ impl CanvasManager {
                             height,
                         },
                         context_type: CanvasContextType::Context2D,
+                        placeholder: None,
+                        webgl: None,
                     });
                 }
             }
@@ -262,9 +307,141 @@ impl CanvasManager {
         }
     }
     
-    /// Get pixel data for a canvas
-    pub fn get_image_data(&self, id: u64) -> Option<&[u8]> {
-        self.canvases.get(&id).map(|c| c.context.data())
+    /// `transferControlToOffscreen()`: hand rendering of a canvas to an
+    /// OffscreenCanvas, typically posted to a worker
+    pub fn transfer_control_to_offscreen(&mut self, id: u64) -> Result<OffscreenCanvas, CanvasError> {
+        let canvas = self.canvases.get_mut(&id)
+            .ok_or_else(|| CanvasError::InvalidState(format!("no canvas {}", id)))?;
+        if canvas.placeholder.is_some() {
+            return Err(CanvasError::InvalidState("control already transferred".into()));
+        }
+        let (offscreen, placeholder) = OffscreenCanvas::transfer_control(canvas.bounds.width, canvas.bounds.height);
+        canvas.placeholder = Some(placeholder);
+        Ok(offscreen)
+    }
+    
+    /// Copy frames committed by offscreen canvases into their placeholders,
+    /// returning how many canvases changed
+    pub fn commit_offscreen_frames(&mut self) -> usize {
+        let mut updated = 0;
+        for canvas in self.canvases.values_mut() {
+            let Some(frame) = canvas.placeholder.as_ref().and_then(|p| p.take_frame()) else {
+                continue;
+            };
+            if frame.width() != canvas.bounds.width || frame.height() != canvas.bounds.height {
+                canvas.context = CanvasRenderingContext2D::new(frame.width(), frame.height());
+                canvas.bounds.width = frame.width();
+                canvas.bounds.height = frame.height();
+            }
+            canvas.context.data_mut().copy_from_slice(frame.data());
+            updated += 1;
+        }
+        if updated > 0 {
+            log::trace!("Committed {} offscreen canvas frames", updated);
+        }
+        updated
+    }
+    
+    /// `getContext("webgl")` or `getContext("webgl2")`; `None` if the canvas
+    /// already has a different WebGL version or was transferred offscreen
+    pub fn get_context_webgl(&mut self, id: u64, webgl2: bool) -> Option<&mut WebGLRenderingContext> {
+        let canvas = self.canvases.get_mut(&id)?;
+        if canvas.placeholder.is_some() {
+            return None;
+        }
+        if let Some(existing) = &canvas.webgl {
+            if existing.is_webgl2() != webgl2 {
+                return None;
+            }
+        } else {
+            let (width, height) = (canvas.bounds.width, canvas.bounds.height);
+            canvas.webgl = Some(if webgl2 {
+                WebGLRenderingContext::new_webgl2(width, height)
+            } else {
+                WebGLRenderingContext::new(width, height)
+            });
+            canvas.context_type = if webgl2 { CanvasContextType::WebGL2 } else { CanvasContextType::WebGL };
+        }
+        canvas.webgl.as_mut()
+    }
+    
+    /// Copy each WebGL drawing buffer into its canvas layer, returning how
+    /// many canvases were composited
+    pub fn composite_webgl_frames(&mut self) -> usize {
+        let mut composited = 0;
+        for canvas in self.canvases.values_mut() {
+            let Some(gl) = canvas.webgl.as_mut() else { continue };
+            let frame = gl.take_frame();
+            let data = canvas.context.data_mut();
+            if frame.len() == data.len() {
+                data.copy_from_slice(&frame);
+                composited += 1;
+            }
+        }
+        composited
+    }
+    
+    /// `captureStream(frameRate)`: a stream with a video track of the
+    /// canvas contents
+    pub fn capture_stream(&mut self, id: u64, frame_rate: Option<f64>, now: Duration) -> Result<MediaStream, CanvasError> {
+        if !self.canvases.contains_key(&id) {
+            return Err(CanvasError::InvalidState(format!("no canvas {}", id)));
+        }
+        // Canvases are not tainted by cross-origin images yet
+        let (stream, track) = CanvasCaptureTrack::capture_stream(frame_rate, true, now).map_err(|e| match e {
+            CanvasCaptureError::NotSupported => CanvasError::NotSupported(e.to_string()),
+            CanvasCaptureError::Security => CanvasError::InvalidState(e.to_string()),
+        })?;
+        self.captures.entry(id).or_insert_with(|| (Vec::new(), 0)).0.push(track);
+        Ok(stream)
+    }
+    
+    /// `CanvasCaptureMediaStreamTrack.requestFrame()`
+    pub fn request_frame(&mut self, track_id: &str) {
+        if let Some(track) = self.captures.values_mut().flat_map(|(tracks, _)| tracks.iter_mut()).find(|t| t.track().id == track_id) {
+            track.request_frame();
+        }
+    }
+    
+    /// `stop()` on a capture track
+    pub fn stop_capture(&mut self, track_id: &str) {
+        for (tracks, _) in self.captures.values_mut() {
+            tracks.retain(|t| t.track().id != track_id);
+        }
+        self.captures.retain(|_, (tracks, _)| !tracks.is_empty());
+    }
+    
+    /// Frames due from canvases whose pixels changed since the last call,
+    /// by track ID. Called after painting each frame.
+    pub fn capture_frames(&mut self, now: Duration) -> Vec<(String, CapturedFrame)> {
+        let mut frames = Vec::new();
+        for (id, (tracks, last_hash)) in &mut self.captures {
+            let Some(canvas) = self.canvases.get(id) else { continue };
+            let data = canvas.context.data();
+            let mut hasher = DefaultHasher::new();
+            data.hash(&mut hasher);
+            let hash = hasher.finish();
+            if hash == *last_hash {
+                continue;
+            }
+            *last_hash = hash;
+            let mut data = data.to_vec();
+            self.fingerprinting.add_canvas_noise(&self.page_url, &mut data);
+            for track in tracks.iter_mut() {
+                if let Some(frame) = track.canvas_changed(&data, canvas.bounds.width, canvas.bounds.height, now) {
+                    frames.push((track.track().id.clone(), frame));
+                }
+            }
+        }
+        frames
+    }
+    
+    /// Pixel data of a canvas as the page reads it back (`getImageData`,
+    /// `toDataURL`), with noise when fingerprinting resistance applies
+    pub fn get_image_data(&self, id: u64) -> Option<Vec<u8>> {
+        let mut data = self.canvases.get(&id)?.context.data().to_vec();
+        self.fingerprinting.add_canvas_noise(&self.page_url, &mut data);
+        Some(data)
     }
     
     /// Get canvas statistics
@@ -308,4 +485,109 @@ mod tests {
         assert_eq!(stats.canvas_count, 0);
         assert_eq!(stats.total_pixels, 0);
     }
+    
+    #[test]
+    fn test_offscreen_commit() {
+        let mut manager = CanvasManager::new();
+        manager.canvases.insert(1, CanvasInstance {
+            id: 1,
+            context: CanvasRenderingContext2D::new(4, 4),
+            bounds: CanvasBounds { x: 0.0, y: 0.0, width: 4, height: 4 },
+            context_type: CanvasContextType::Context2D,
+            placeholder: None,
+            webgl: None,
+        });
+        
+        let mut offscreen = manager.transfer_control_to_offscreen(1).unwrap();
+        assert!(manager.transfer_control_to_offscreen(1).is_err());
+        
+        offscreen.get_context_2d().unwrap().fill_rect(0.0, 0.0, 4.0, 4.0);
+        assert_eq!(manager.commit_offscreen_frames(), 0);
+        offscreen.commit();
+        assert_eq!(manager.commit_offscreen_frames(), 1);
+        assert_eq!(&manager.get_image_data(1).unwrap()[..4], &[0, 0, 0, 255]);
+    }
+    
+    #[test]
+    fn test_webgl_composite() {
+        let mut manager = CanvasManager::new();
+        manager.canvases.insert(1, CanvasInstance {
+            id: 1,
+            context: CanvasRenderingContext2D::new(2, 2),
+            bounds: CanvasBounds { x: 0.0, y: 0.0, width: 2, height: 2 },
+            context_type: CanvasContextType::Context2D,
+            placeholder: None,
+            webgl: None,
+        });
+        
+        let gl = manager.get_context_webgl(1, false).unwrap();
+        gl.clear_color(0.0, 1.0, 0.0, 1.0);
+        gl.clear(fos_canvas::webgl::GL_COLOR_BUFFER_BIT);
+        assert!(manager.get_context_webgl(1, true).is_none());
+        
+        assert_eq!(manager.composite_webgl_frames(), 1);
+        assert_eq!(manager.get_canvas(1).unwrap().context_type, CanvasContextType::WebGL);
+        assert_eq!(&manager.get_image_data(1).unwrap()[..4], &[0, 255, 0, 255]);
+    }
+    
+    #[test]
+    fn test_capture_stream() {
+        let mut manager = CanvasManager::new();
+        manager.canvases.insert(1, CanvasInstance {
+            id: 1,
+            context: CanvasRenderingContext2D::new(2, 2),
+            bounds: CanvasBounds { x: 0.0, y: 0.0, width: 2, height: 2 },
+            context_type: CanvasContextType::Context2D,
+            placeholder: None,
+            webgl: None,
+        });
+        assert!(manager.capture_stream(2, None, Duration::ZERO).is_err());
+        let stream = manager.capture_stream(1, None, Duration::ZERO).unwrap();
+        let track_id = stream.tracks[0].id.clone();
+        
+        manager.fill_rect(1, 0.0, 0.0, 2.0, 2.0);
+        let frames = manager.capture_frames(Duration::from_millis(16));
+        assert_eq!(frames.len(), 1);
+        assert_eq!((frames[0].0.as_str(), frames[0].1.width), (track_id.as_str(), 2));
+        // Nothing new is drawn, so no frame is captured
+        assert!(manager.capture_frames(Duration::from_millis(32)).is_empty());
+        
+        manager.stop_capture(&track_id);
+        manager.clear(1);
+        assert!(manager.capture_frames(Duration::from_millis(48)).is_empty());
+    }
+    
+    #[test]
+    fn test_fingerprinting_resistance() {
+        let mut manager = CanvasManager::new();
+        manager.canvases.insert(1, CanvasInstance {
+            id: 1,
+            context: CanvasRenderingContext2D::new(16, 16),
+            bounds: CanvasBounds { x: 0.0, y: 0.0, width: 16, height: 16 },
+            context_type: CanvasContextType::Context2D,
+            placeholder: None,
+            webgl: None,
+        });
+        manager.fill_rect(1, 0.0, 0.0, 16.0, 16.0);
+        let pixels = manager.get_canvas(1).unwrap().context.data().to_vec();
+        let mut fingerprinting = FingerprintingProtection::new();
+        manager.set_page("https://example.com/", fingerprinting.clone());
+        assert_eq!(manager.get_image_data(1).unwrap(), pixels);
+        
+        // Reads get noise, the canvas itself keeps its pixels
+        fingerprinting.enabled = true;
+        manager.set_page("https://example.com/", fingerprinting.clone());
+        let read = manager.get_image_data(1).unwrap();
+        assert_ne!(read, pixels);
+        assert_eq!(manager.get_image_data(1).unwrap(), read);
+        assert_eq!(manager.get_canvas(1).unwrap().context.data(), &pixels[..]);
+        
+        // Captured frames get noise too
+        manager.capture_stream(1, None, Duration::ZERO).unwrap();
+        let noisy = manager.capture_frames(Duration::from_millis(16)).remove(0).1;
+        manager.set_page("https://example.com/", FingerprintingProtection::new());
+        manager.captures.get_mut(&1).unwrap().1 = 0;
+        let plain = manager.capture_frames(Duration::from_millis(32)).remove(0).1;
+        assert_ne!(noisy.data, plain.data);
+    }
 }
@@ -0,0 +1,115 @@
+//! Certificate Error Overrides
+//!
+//! Hosts the user chose to visit despite a certificate error, kept across
+//! sessions. The network layer connects to them without failing on their
+//! certificate; hosts with an HSTS policy are never let through.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Per-host certificate error overrides, saved to disk
+#[derive(Debug, Default)]
+pub struct CertificateOverrides {
+    /// Error code the user accepted, by host
+    hosts: HashMap<String, String>,
+    storage_path: Option<PathBuf>,
+}
+
+impl CertificateOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Create with persistence
+    pub fn with_storage(path: PathBuf) -> Self {
+        let mut overrides = Self::new();
+        overrides.storage_path = Some(path);
+        overrides.load();
+        overrides
+    }
+    
+    /// Let `host` through despite the certificate error `code`
+    pub fn add(&mut self, host: &str, code: &str) {
+        self.hosts.insert(host.to_ascii_lowercase(), code.to_string());
+        self.save();
+    }
+    
+    /// Whether the user let `host` through
+    pub fn allows(&self, host: &str) -> bool {
+        self.hosts.contains_key(&host.to_ascii_lowercase())
+    }
+    
+    /// Error code accepted for `host`
+    pub fn code(&self, host: &str) -> Option<&str> {
+        self.hosts.get(&host.to_ascii_lowercase()).map(String::as_str)
+    }
+    
+    /// Hosts with an override
+    pub fn hosts(&self) -> impl Iterator<Item = &str> {
+        self.hosts.keys().map(String::as_str)
+    }
+    
+    /// Withdraw the override for `host`
+    pub fn remove(&mut self, host: &str) -> bool {
+        let removed = self.hosts.remove(&host.to_ascii_lowercase()).is_some();
+        if removed {
+            self.save();
+        }
+        removed
+    }
+    
+    /// Save to disk
+    pub fn save(&self) {
+        let Some(path) = &self.storage_path else { return };
+        
+        let mut data = String::new();
+        for (host, code) in &self.hosts {
+            data.push_str(&format!("{}\t{}\n", host, code));
+        }
+        
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = fs::write(path, data) {
+            log::warn!("Failed to save certificate overrides to {}: {}", path.display(), e);
+        }
+    }
+    
+    /// Load from disk
+    pub fn load(&mut self) {
+        let Some(path) = &self.storage_path else { return };
+        
+        let data = match fs::read_to_string(path) {
+            Ok(d) => d,
+            Err(_) => return,
+        };
+        
+        for line in data.lines() {
+            if let Some((host, code)) = line.split_once('\t') {
+                self.hosts.insert(host.to_string(), code.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_overrides_persist() {
+        let path = std::env::temp_dir().join(format!("fos-cert-overrides-test-{}", std::process::id()));
+        let mut overrides = CertificateOverrides::with_storage(path.clone());
+        overrides.add("Self-Signed.example", "ERR_CERT_AUTHORITY_INVALID");
+        assert!(overrides.allows("self-signed.example"));
+        assert!(!overrides.allows("example.com"));
+        
+        let loaded = CertificateOverrides::with_storage(path.clone());
+        assert_eq!(loaded.code("self-signed.example"), Some("ERR_CERT_AUTHORITY_INVALID"));
+        
+        overrides.remove("self-signed.example");
+        assert!(!CertificateOverrides::with_storage(path.clone()).allows("self-signed.example"));
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,204 @@
+//! Runtime Configuration
+//!
+//! Engine flags the user can change from about:config. Changes apply from
+//! the next page load; flags set away from their defaults are saved to
+//! disk, so resetting a flag forgets it.
+
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use fos_engine::{Config, ProcessModel};
+
+/// Flags about:config offers, with what they control
+pub const CONFIG_FLAGS: [(&str, &str); 4] = [
+    ("enable_javascript", "Run page scripts"),
+    ("user_agent", "User agent sent with requests"),
+    ("max_memory", "Most memory one page may use, in bytes"),
+    ("process_model", "Which tabs share a renderer: process-per-site, process-per-tab or single-process"),
+];
+
+/// Value of a config flag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlagValue {
+    Bool(bool),
+    Integer(usize),
+    Text(String),
+}
+
+impl fmt::Display for FlagValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Text(value) => f.write_str(value),
+        }
+    }
+}
+
+/// A config flag as about:config lists it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFlag {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub value: FlagValue,
+    /// Set away from the default
+    pub modified: bool,
+}
+
+/// Why a flag could not be changed
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Unknown config flag: {0}")]
+    UnknownFlag(String),
+    
+    #[error("Invalid value for {0}: {1}")]
+    InvalidValue(String, String),
+}
+
+/// Engine config, saved to disk
+#[derive(Debug, Default)]
+pub struct ConfigStore {
+    config: Config,
+    storage_path: Option<PathBuf>,
+}
+
+impl ConfigStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Create with persistence
+    pub fn with_storage(path: PathBuf) -> Self {
+        let mut store = Self::new();
+        store.storage_path = Some(path);
+        store.load();
+        store
+    }
+    
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+    
+    /// Every flag with its current value
+    pub fn flags(&self) -> Vec<ConfigFlag> {
+        let defaults = Config::default();
+        CONFIG_FLAGS.iter()
+            .filter_map(|&(name, description)| {
+                let value = flag_value(&self.config, name)?;
+                Some(ConfigFlag {
+                    name,
+                    description,
+                    modified: flag_value(&defaults, name).as_ref() != Some(&value),
+                    value,
+                })
+            })
+            .collect()
+    }
+    
+    /// Set a flag from its text form, e.g. "false" or "1048576"
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), ConfigError> {
+        set_flag(&mut self.config, name, value)?;
+        self.save();
+        Ok(())
+    }
+    
+    /// Put a flag back to its default
+    pub fn reset(&mut self, name: &str) -> Result<(), ConfigError> {
+        let default = flag_value(&Config::default(), name).ok_or_else(|| ConfigError::UnknownFlag(name.to_string()))?;
+        self.set(name, &default.to_string())
+    }
+    
+    /// Save modified flags to disk
+    pub fn save(&self) {
+        let Some(path) = &self.storage_path else { return };
+        
+        let mut data = String::new();
+        for flag in self.flags().into_iter().filter(|flag| flag.modified) {
+            data.push_str(&format!("{}\t{}\n", flag.name, flag.value));
+        }
+        
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = fs::write(path, data) {
+            log::warn!("Failed to save config to {}: {}", path.display(), e);
+        }
+    }
+    
+    /// Load from disk
+    pub fn load(&mut self) {
+        let Some(path) = &self.storage_path else { return };
+        
+        let data = match fs::read_to_string(path) {
+            Ok(d) => d,
+            Err(_) => return,
+        };
+        
+        for line in data.lines() {
+            if let Some((name, value)) = line.split_once('\t') {
+                if let Err(e) = set_flag(&mut self.config, name, value) {
+                    log::warn!("Ignoring saved config: {}", e);
+                }
+            }
+        }
+    }
+}
+
+fn flag_value(config: &Config, name: &str) -> Option<FlagValue> {
+    Some(match name {
+        "enable_javascript" => FlagValue::Bool(config.enable_javascript),
+        "user_agent" => FlagValue::Text(config.user_agent.clone()),
+        "max_memory" => FlagValue::Integer(config.max_memory),
+        "process_model" => FlagValue::Text(config.process_model.as_arg().to_string()),
+        _ => return None,
+    })
+}
+
+fn set_flag(config: &mut Config, name: &str, value: &str) -> Result<(), ConfigError> {
+    let invalid = || ConfigError::InvalidValue(name.to_string(), value.to_string());
+    match name {
+        "enable_javascript" => config.enable_javascript = value.parse().map_err(|_| invalid())?,
+        "user_agent" => {
+            // Header values end at a line break
+            if value.trim().is_empty() || value.contains(['\r', '\n']) {
+                return Err(invalid());
+            }
+            config.user_agent = value.trim().to_string();
+        }
+        "max_memory" => config.max_memory = value.parse().map_err(|_| invalid())?,
+        "process_model" => config.process_model = ProcessModel::from_arg(value.trim()).ok_or_else(invalid)?,
+        _ => return Err(ConfigError::UnknownFlag(name.to_string())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_config_flags_persist() {
+        let path = std::env::temp_dir().join(format!("fos-config-test-{}", std::process::id()));
+        let mut store = ConfigStore::with_storage(path.clone());
+        assert!(store.flags().iter().all(|flag| !flag.modified));
+        
+        store.set("enable_javascript", "false").unwrap();
+        store.set("max_memory", "1048576").unwrap();
+        assert!(matches!(store.set("max_memory", "lots"), Err(ConfigError::InvalidValue(..))));
+        store.set("process_model", "process-per-site").unwrap();
+        assert!(matches!(store.set("process_model", "per-frame"), Err(ConfigError::InvalidValue(..))));
+        assert!(matches!(store.set("no_such_flag", "1"), Err(ConfigError::UnknownFlag(_))));
+        
+        let loaded = ConfigStore::with_storage(path.clone());
+        assert!(!loaded.config().enable_javascript);
+        assert_eq!(loaded.config().max_memory, 1048576);
+        assert_eq!(loaded.config().process_model, ProcessModel::ProcessPerSite);
+        let modified: Vec<_> = loaded.flags().into_iter().filter(|f| f.modified).map(|f| f.name).collect();
+        assert_eq!(modified, ["enable_javascript", "max_memory", "process_model"]);
+        
+        store.reset("enable_javascript").unwrap();
+        assert!(ConfigStore::with_storage(path.clone()).config().enable_javascript);
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,314 @@
+//! Contact Picker API
+//!
+//! `navigator.contacts.select()` over a platform address book. The page
+//! never sees the address book itself: the browser shows a picker and only
+//! the contacts and properties the user chose are returned.
+
+use std::collections::HashSet;
+
+/// Contact property that can be requested
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContactProperty {
+    Address,
+    Email,
+    Icon,
+    Name,
+    Tel,
+}
+
+impl ContactProperty {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "address" => Some(Self::Address),
+            "email" => Some(Self::Email),
+            "icon" => Some(Self::Icon),
+            "name" => Some(Self::Name),
+            "tel" => Some(Self::Tel),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Address => "address",
+            Self::Email => "email",
+            Self::Icon => "icon",
+            Self::Name => "name",
+            Self::Tel => "tel",
+        }
+    }
+}
+
+/// Postal address of a contact
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContactAddress {
+    pub country: String,
+    pub address_line: Vec<String>,
+    pub region: String,
+    pub city: String,
+    pub postal_code: String,
+}
+
+/// Contact icon
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContactIcon {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// A contact; only requested properties are filled when returned to a page
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContactInfo {
+    pub name: Vec<String>,
+    pub email: Vec<String>,
+    pub tel: Vec<String>,
+    pub address: Vec<ContactAddress>,
+    pub icon: Vec<ContactIcon>,
+}
+
+impl ContactInfo {
+    /// Copy with everything but `properties` removed
+    fn restricted_to(&self, properties: &[ContactProperty]) -> Self {
+        let has = |p| properties.contains(&p);
+        Self {
+            name: if has(ContactProperty::Name) { self.name.clone() } else { Vec::new() },
+            email: if has(ContactProperty::Email) { self.email.clone() } else { Vec::new() },
+            tel: if has(ContactProperty::Tel) { self.tel.clone() } else { Vec::new() },
+            address: if has(ContactProperty::Address) { self.address.clone() } else { Vec::new() },
+            icon: if has(ContactProperty::Icon) { self.icon.clone() } else { Vec::new() },
+        }
+    }
+
+    /// Line shown in the picker
+    pub fn display_name(&self) -> &str {
+        self.name.first()
+            .or(self.email.first())
+            .or(self.tel.first())
+            .map(String::as_str)
+            .unwrap_or("(no name)")
+    }
+}
+
+/// Contacts error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContactsError {
+    /// No contacts provider on this platform
+    NotSupported,
+    /// Empty or unsupported property list
+    TypeError,
+    /// No user activation, not a top-level frame, or access blocked
+    NotAllowed,
+    /// A picker is already open
+    InvalidState,
+}
+
+impl std::fmt::Display for ContactsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotSupported => write!(f, "Contact Picker not supported"),
+            Self::TypeError => write!(f, "Invalid contact properties"),
+            Self::NotAllowed => write!(f, "Contact access not allowed"),
+            Self::InvalidState => write!(f, "Contact picker already open"),
+        }
+    }
+}
+
+impl std::error::Error for ContactsError {}
+
+/// Platform address book, implemented by embedders
+pub trait ContactsProvider: Send + std::fmt::Debug {
+    /// Properties this address book can supply
+    fn supported_properties(&self) -> Vec<ContactProperty>;
+
+    /// Ask the OS for address book access (e.g. READ_CONTACTS); called
+    /// before the picker opens
+    fn request_access(&mut self) -> bool { true }
+
+    fn contacts(&self) -> Vec<ContactInfo>;
+}
+
+/// Picker shown for one `select()` call
+#[derive(Debug, Clone)]
+pub struct ContactPicker {
+    /// Origin shown in the picker title
+    pub origin: String,
+    pub properties: Vec<ContactProperty>,
+    pub multiple: bool,
+    pub contacts: Vec<ContactInfo>,
+    pub selected: Vec<usize>,
+}
+
+impl ContactPicker {
+    /// Select or deselect a contact; single-select pickers replace the selection
+    pub fn toggle(&mut self, index: usize) {
+        if index >= self.contacts.len() {
+            return;
+        }
+        if let Some(pos) = self.selected.iter().position(|&i| i == index) {
+            self.selected.remove(pos);
+        } else if self.multiple {
+            self.selected.push(index);
+        } else {
+            self.selected = vec![index];
+        }
+    }
+
+    pub fn is_selected(&self, index: usize) -> bool {
+        self.selected.contains(&index)
+    }
+}
+
+/// Contact Picker manager
+#[derive(Debug, Default)]
+pub struct ContactsManager {
+    provider: Option<Box<dyn ContactsProvider>>,
+    picker: Option<ContactPicker>,
+    /// Origins the user blocked from opening the picker
+    blocked_origins: HashSet<String>,
+}
+
+impl ContactsManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_provider(&mut self, provider: Box<dyn ContactsProvider>) {
+        self.provider = Some(provider);
+    }
+
+    /// Whether `navigator.contacts` is exposed
+    pub fn is_supported(&self) -> bool {
+        self.provider.is_some()
+    }
+
+    /// `getProperties()`
+    pub fn get_properties(&self) -> Vec<ContactProperty> {
+        self.provider.as_ref().map(|p| p.supported_properties()).unwrap_or_default()
+    }
+
+    /// Block or unblock an origin (site settings)
+    pub fn set_origin_blocked(&mut self, origin: &str, blocked: bool) {
+        if blocked {
+            self.blocked_origins.insert(origin.to_string());
+        } else {
+            self.blocked_origins.remove(origin);
+        }
+    }
+
+    /// `select(properties, { multiple })`: open the picker
+    pub fn select(
+        &mut self,
+        origin: &str,
+        properties: &[ContactProperty],
+        multiple: bool,
+        user_activation: bool,
+        top_level: bool,
+    ) -> Result<(), ContactsError> {
+        let provider = self.provider.as_mut().ok_or(ContactsError::NotSupported)?;
+        if !top_level || !user_activation || self.blocked_origins.contains(origin) {
+            return Err(ContactsError::NotAllowed);
+        }
+        if self.picker.is_some() {
+            return Err(ContactsError::InvalidState);
+        }
+        let supported = provider.supported_properties();
+        if properties.is_empty() || properties.iter().any(|p| !supported.contains(p)) {
+            return Err(ContactsError::TypeError);
+        }
+        if !provider.request_access() {
+            return Err(ContactsError::NotAllowed);
+        }
+
+        let mut props = properties.to_vec();
+        props.dedup();
+        self.picker = Some(ContactPicker {
+            origin: origin.to_string(),
+            properties: props,
+            multiple,
+            contacts: provider.contacts(),
+            selected: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Open picker, for rendering
+    pub fn picker(&self) -> Option<&ContactPicker> {
+        self.picker.as_ref()
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(picker) = &mut self.picker {
+            picker.toggle(index);
+        }
+    }
+
+    /// User confirmed: the chosen contacts, reduced to the requested properties
+    pub fn confirm(&mut self) -> Vec<ContactInfo> {
+        let Some(picker) = self.picker.take() else { return Vec::new() };
+        picker.selected.iter()
+            .filter_map(|&i| picker.contacts.get(i))
+            .map(|c| c.restricted_to(&picker.properties))
+            .collect()
+    }
+
+    /// User dismissed the picker; `select()` resolves with no contacts
+    pub fn cancel(&mut self) -> Vec<ContactInfo> {
+        self.picker = None;
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct AddressBook;
+
+    impl ContactsProvider for AddressBook {
+        fn supported_properties(&self) -> Vec<ContactProperty> {
+            vec![ContactProperty::Name, ContactProperty::Email, ContactProperty::Tel]
+        }
+
+        fn contacts(&self) -> Vec<ContactInfo> {
+            vec![
+                ContactInfo { name: vec!["Ada".into()], email: vec!["ada@example.com".into()], tel: vec!["+1 555 0100".into()], ..Default::default() },
+                ContactInfo { name: vec!["Grace".into()], email: vec!["grace@example.com".into()], ..Default::default() },
+            ]
+        }
+    }
+
+    #[test]
+    fn test_select_contacts() {
+        let mut contacts = ContactsManager::new();
+        assert_eq!(contacts.select("https://a.example", &[ContactProperty::Name], false, true, true), Err(ContactsError::NotSupported));
+        contacts.set_provider(Box::new(AddressBook));
+
+        assert_eq!(contacts.select("https://a.example", &[ContactProperty::Name], false, false, true), Err(ContactsError::NotAllowed));
+        assert_eq!(contacts.select("https://a.example", &[ContactProperty::Icon], false, true, true), Err(ContactsError::TypeError));
+
+        contacts.select("https://a.example", &[ContactProperty::Name, ContactProperty::Email], true, true, true).unwrap();
+        assert_eq!(contacts.select("https://a.example", &[ContactProperty::Name], false, true, true), Err(ContactsError::InvalidState));
+        contacts.toggle(0);
+        contacts.toggle(1);
+        let chosen = contacts.confirm();
+        assert_eq!(chosen.len(), 2);
+        // Phone numbers were not requested
+        assert!(chosen[0].tel.is_empty());
+        assert_eq!(chosen[1].email, vec!["grace@example.com".to_string()]);
+    }
+
+    #[test]
+    fn test_single_select_and_blocking() {
+        let mut contacts = ContactsManager::new();
+        contacts.set_provider(Box::new(AddressBook));
+        contacts.select("https://a.example", &[ContactProperty::Tel], false, true, true).unwrap();
+        contacts.toggle(0);
+        contacts.toggle(1);
+        assert_eq!(contacts.picker().unwrap().selected, vec![1]);
+        assert!(contacts.cancel().is_empty());
+
+        contacts.set_origin_blocked("https://a.example", true);
+        assert_eq!(contacts.select("https://a.example", &[ContactProperty::Tel], false, true, true), Err(ContactsError::NotAllowed));
+    }
+}
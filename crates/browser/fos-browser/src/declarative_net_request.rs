@@ -0,0 +1,351 @@
+//! Declarative Net Request
+//!
+//! Request rules from extensions' static rulesets, compiled into the same
+//! [`RuleMatcher`] as tracking protection, each extension under its own
+//! source. [`NetRequestRules`] is a stage of the interception pipeline, so
+//! it sees every request before the cache or the network does.
+//!
+//! Block and allow rules need the "declarativeNetRequest" permission.
+//! Redirect and header rules, and every rule of an extension with only
+//! "declarativeNetRequestWithHostAccess", apply to URLs its host
+//! permissions match.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use fos_js::HostValue;
+use fos_net::{Interception, Request, RequestInterceptor};
+use fos_security::{
+    DomainType, HeaderOp, HeaderOperation, RequestDetails, ResourceType, Rule, RuleAction, RuleCondition,
+    RuleMatcher, RuleOutcome, RuleVerdict, TrackingProtection, TRACKING_PROTECTION_SOURCE,
+};
+use crate::extensions::{Extension, ExtensionError, MatchPattern, Permission};
+
+/// Rules an extension's enabled static rulesets hold at most
+pub const MAX_STATIC_RULES: usize = 30_000;
+
+/// Static rulesets an extension may have enabled at once
+pub const MAX_ENABLED_RULESETS: usize = 50;
+
+/// What an extension's rules may do
+#[derive(Debug, Clone)]
+struct RuleAccess {
+    /// Block and allow anywhere
+    everywhere: bool,
+    host_permissions: Vec<MatchPattern>,
+}
+
+impl RuleAccess {
+    fn allows(&self, rule: &Rule, url: &str) -> bool {
+        let needs_host = !self.everywhere || matches!(rule.action, RuleAction::Redirect(_) | RuleAction::ModifyHeaders { .. });
+        !needs_host || self.host_permissions.iter().any(|p| p.matches(url))
+    }
+}
+
+#[derive(Debug, Default)]
+struct RulesState {
+    matcher: RuleMatcher,
+    access: HashMap<String, RuleAccess>,
+}
+
+/// Tracking protection and extension request rules
+#[derive(Debug, Default)]
+pub struct NetRequestRules {
+    state: RwLock<RulesState>,
+}
+
+impl NetRequestRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Follow tracking protection's blocklist, or drop it when disabled
+    pub fn set_tracking_protection(&self, tracking: &TrackingProtection) {
+        let mut state = self.state.write().unwrap();
+        if !tracking.enabled {
+            state.matcher.remove_rules(TRACKING_PROTECTION_SOURCE);
+        } else if let Err(e) = state.matcher.set_rules(TRACKING_PROTECTION_SOURCE, tracking.rules()) {
+            log::warn!("Tracking protection rules: {}", e);
+        }
+    }
+    
+    /// Compile an extension's enabled static rulesets, replacing any it had,
+    /// and return how many rules it has
+    pub fn load_extension(&self, ext: &Extension) -> Result<usize, ExtensionError> {
+        let everywhere = ext.has_permission(&Permission::DeclarativeNetRequest);
+        if !everywhere && !ext.has_permission(&Permission::DeclarativeNetRequestWithHostAccess) {
+            return Err(ExtensionError::PermissionDenied);
+        }
+        
+        let enabled: Vec<_> = ext.manifest.declarative_net_request.iter().filter(|r| r.enabled).collect();
+        if enabled.len() > MAX_ENABLED_RULESETS {
+            return Err(ExtensionError::InvalidManifest(format!("more than {} enabled rulesets", MAX_ENABLED_RULESETS)));
+        }
+        let mut rules = Vec::new();
+        for ruleset in enabled {
+            let json = ext.resources.get(&ruleset.path).ok_or_else(|| {
+                ExtensionError::InvalidManifest(format!("ruleset {}: missing file {}", ruleset.id, ruleset.path))
+            })?;
+            let parsed = parse_rules(&ext.id, json)
+                .map_err(|e| ExtensionError::InvalidManifest(format!("ruleset {}: {}", ruleset.id, e)))?;
+            rules.extend(parsed);
+            if rules.len() > MAX_STATIC_RULES {
+                return Err(ExtensionError::InvalidManifest(format!("more than {} static rules", MAX_STATIC_RULES)));
+            }
+        }
+        
+        // Rule IDs are unique per ruleset; number them across the extension
+        let count = rules.len();
+        for (i, rule) in rules.iter_mut().enumerate() {
+            rule.id = i as u32 + 1;
+        }
+        let host_permissions = ext.manifest.permissions.iter()
+            .filter_map(|p| match p {
+                Permission::Host(pattern) => MatchPattern::parse(pattern).ok(),
+                _ => None,
+            })
+            .collect();
+        
+        let mut state = self.state.write().unwrap();
+        state.matcher.set_rules(&ext.id, rules)
+            .map_err(|e| ExtensionError::InvalidManifest(e.to_string()))?;
+        state.access.insert(ext.id.clone(), RuleAccess { everywhere, host_permissions });
+        log::info!("Loaded {} request rules from extension {}", count, ext.id);
+        Ok(count)
+    }
+    
+    /// Drop an unloaded or disabled extension's rules
+    pub fn unload_extension(&self, ext_id: &str) {
+        let mut state = self.state.write().unwrap();
+        state.matcher.remove_rules(ext_id);
+        state.access.remove(ext_id);
+    }
+    
+    /// Rules an extension has loaded
+    pub fn rule_count(&self, ext_id: &str) -> usize {
+        self.state.read().unwrap().matcher.rule_count(ext_id)
+    }
+    
+    /// Verdict on a request, each extension's rules limited to what its
+    /// permissions allow
+    pub fn evaluate(&self, request: &RequestDetails) -> RuleVerdict {
+        let state = self.state.read().unwrap();
+        state.matcher.evaluate_filtered(request, |source, rule| {
+            state.access.get(source).is_none_or(|access| access.allows(rule, request.url))
+        })
+    }
+    
+    fn verdict_for(&self, request: &Request) -> RuleVerdict {
+        let resource_type = match request.resource_type {
+            fos_net::ResourceType::Document => ResourceType::MainFrame,
+            fos_net::ResourceType::Style => ResourceType::Stylesheet,
+            fos_net::ResourceType::Script => ResourceType::Script,
+            fos_net::ResourceType::Image => ResourceType::Image,
+            fos_net::ResourceType::Font => ResourceType::Font,
+            fos_net::ResourceType::Media => ResourceType::Media,
+            fos_net::ResourceType::Prefetch | fos_net::ResourceType::Other => ResourceType::Other,
+        };
+        let mut details = RequestDetails::new(&request.url, resource_type);
+        if let Some(initiator) = &request.initiator {
+            details = details.with_initiator(initiator);
+        }
+        self.evaluate(&details)
+    }
+}
+
+impl RequestInterceptor for NetRequestRules {
+    fn intercept_request(&self, request: &mut Request) -> Interception {
+        let verdict = self.verdict_for(request);
+        match verdict.outcome {
+            RuleOutcome::Block => {
+                log::debug!("Request rules blocked {}", request.url);
+                Interception::Block
+            }
+            RuleOutcome::Redirect(url) => Interception::Redirect(url),
+            RuleOutcome::Continue => {
+                if !verdict.request_headers.is_empty() {
+                    let mut headers: Vec<(String, String)> = request.headers.drain().collect();
+                    HeaderOperation::apply_all(&verdict.request_headers, &mut headers);
+                    // Appended values join the existing header
+                    for (name, value) in headers {
+                        request.headers.entry(name)
+                            .and_modify(|v| { v.push_str(", "); v.push_str(&value); })
+                            .or_insert(value);
+                    }
+                }
+                Interception::Continue
+            }
+        }
+    }
+    
+    fn intercept_response(&self, request: &Request, headers: &mut Vec<(String, String)>) {
+        HeaderOperation::apply_all(&self.verdict_for(request).response_headers, headers);
+    }
+}
+
+/// Parse a static ruleset file: a JSON array of rules as
+/// `declarativeNetRequest` writes them
+pub fn parse_rules(ext_id: &str, json: &str) -> Result<Vec<Rule>, String> {
+    let Some(HostValue::Array(items)) = HostValue::from_json(json) else {
+        return Err("rules must be a JSON array".to_string());
+    };
+    items.iter().map(|item| parse_rule(ext_id, item)).collect()
+}
+
+fn parse_rule(ext_id: &str, item: &HostValue) -> Result<Rule, String> {
+    let number = |key: &str| item.get(key).and_then(HostValue::as_number).filter(|n| n.fract() == 0.0 && *n >= 1.0);
+    let id = number("id").ok_or("rule needs a positive integer id")? as u32;
+    let priority = match item.get("priority") {
+        Some(_) => number("priority").ok_or_else(|| format!("rule {}: priority must be a positive integer", id))? as u32,
+        None => 1,
+    };
+    let action = item.get("action").ok_or_else(|| format!("rule {}: missing action", id))?;
+    let condition = item.get("condition").ok_or_else(|| format!("rule {}: missing condition", id))?;
+    Ok(Rule {
+        id,
+        priority,
+        action: parse_action(ext_id, action).map_err(|e| format!("rule {}: {}", id, e))?,
+        condition: parse_condition(condition).map_err(|e| format!("rule {}: {}", id, e))?,
+    })
+}
+
+fn parse_action(ext_id: &str, action: &HostValue) -> Result<RuleAction, String> {
+    let headers = |key: &str| -> Result<Vec<HeaderOperation>, String> {
+        let Some(HostValue::Array(items)) = action.get(key) else { return Ok(Vec::new()) };
+        items.iter().map(|item| {
+            let header = item.get("header").and_then(HostValue::as_str).ok_or("header operation needs a header")?;
+            let value = item.get("value").and_then(HostValue::as_str).map(str::to_string);
+            let op = match (item.get("operation").and_then(HostValue::as_str), value) {
+                (Some("set"), Some(value)) => HeaderOp::Set(value),
+                (Some("append"), Some(value)) => HeaderOp::Append(value),
+                (Some("remove"), None) => HeaderOp::Remove,
+                (operation, _) => return Err(format!("bad operation {:?} on header {}", operation.unwrap_or_default(), header)),
+            };
+            Ok(HeaderOperation::new(header, op))
+        }).collect()
+    };
+    
+    match action.get("type").and_then(HostValue::as_str) {
+        Some("block") => Ok(RuleAction::Block),
+        Some("allow") => Ok(RuleAction::Allow),
+        Some("redirect") => {
+            let redirect = action.get("redirect").ok_or("redirect action needs a redirect")?;
+            if let Some(url) = redirect.get("url").and_then(HostValue::as_str) {
+                Ok(RuleAction::Redirect(url.to_string()))
+            } else if let Some(path) = redirect.get("extensionPath").and_then(HostValue::as_str) {
+                Ok(RuleAction::Redirect(format!("extension://{}/{}", ext_id, path.trim_start_matches('/'))))
+            } else {
+                Err("redirect needs a url or extensionPath".to_string())
+            }
+        }
+        Some("modifyHeaders") => Ok(RuleAction::ModifyHeaders {
+            request_headers: headers("requestHeaders")?,
+            response_headers: headers("responseHeaders")?,
+        }),
+        other => Err(format!("unsupported action type {:?}", other.unwrap_or_default())),
+    }
+}
+
+fn parse_condition(condition: &HostValue) -> Result<RuleCondition, String> {
+    let strings = |key: &str| -> Vec<String> {
+        match condition.get(key) {
+            Some(HostValue::Array(items)) => items.iter().filter_map(HostValue::as_str).map(str::to_string).collect(),
+            _ => Vec::new(),
+        }
+    };
+    let types = |key: &str| -> Result<Vec<ResourceType>, String> {
+        strings(key).iter()
+            .map(|name| ResourceType::parse(name).ok_or_else(|| format!("unknown resource type {}", name)))
+            .collect()
+    };
+    Ok(RuleCondition {
+        url_filter: condition.get("urlFilter").and_then(HostValue::as_str).map(str::to_string),
+        request_domains: strings("requestDomains"),
+        excluded_request_domains: strings("excludedRequestDomains"),
+        initiator_domains: strings("initiatorDomains"),
+        excluded_initiator_domains: strings("excludedInitiatorDomains"),
+        resource_types: types("resourceTypes")?,
+        excluded_resource_types: types("excludedResourceTypes")?,
+        domain_type: match condition.get("domainType").and_then(HostValue::as_str) {
+            Some(name) => Some(DomainType::parse(name).ok_or_else(|| format!("unknown domain type {}", name))?),
+            None => None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::{ExtensionManifest, RulesetDeclaration};
+    
+    const RULES: &str = r#"[
+        {"id": 1, "action": {"type": "block"}, "condition": {"urlFilter": "||ads.example^", "resourceTypes": ["script", "image"]}},
+        {"id": 2, "priority": 2, "action": {"type": "redirect", "redirect": {"extensionPath": "/blank.js"}},
+         "condition": {"urlFilter": "||cdn.example/track.js"}},
+        {"id": 3, "action": {"type": "modifyHeaders",
+         "requestHeaders": [{"header": "Referer", "operation": "remove"}],
+         "responseHeaders": [{"header": "x-frame-options", "operation": "set", "value": "DENY"}]},
+         "condition": {"requestDomains": ["example.com"]}}
+    ]"#;
+    
+    fn extension(permissions: Vec<Permission>) -> Extension {
+        let mut ext = Extension::new("blocker", ExtensionManifest {
+            name: "Blocker".to_string(),
+            version: "1.0".to_string(),
+            description: String::new(),
+            permissions,
+            content_scripts: vec![],
+            background: None,
+            browser_action: None,
+            page_action: None,
+            declarative_net_request: vec![RulesetDeclaration {
+                id: "default".to_string(),
+                enabled: true,
+                path: "rules.json".to_string(),
+            }],
+        });
+        ext.resources.insert("rules.json".to_string(), RULES.to_string());
+        ext
+    }
+    
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules("blocker", RULES).unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0].condition.resource_types, vec![ResourceType::Script, ResourceType::Image]);
+        assert_eq!(rules[1].action, RuleAction::Redirect("extension://blocker/blank.js".to_string()));
+        assert!(parse_rules("blocker", r#"[{"id": 1, "action": {"type": "upgradeScheme"}, "condition": {}}]"#).is_err());
+        assert!(parse_rules("blocker", r#"[{"id": 0, "action": {"type": "block"}, "condition": {}}]"#).is_err());
+        let third_party = parse_rules("blocker", r#"[{"id": 1, "action": {"type": "block"}, "condition": {"domainType": "thirdParty"}}]"#).unwrap();
+        assert_eq!(third_party[0].condition.domain_type, Some(DomainType::ThirdParty));
+        assert!(parse_rules("blocker", r#"[{"id": 1, "action": {"type": "block"}, "condition": {"domainType": "other"}}]"#).is_err());
+    }
+    
+    #[test]
+    fn test_interception() {
+        let rules = NetRequestRules::new();
+        let mut tracking = TrackingProtection::new();
+        tracking.enable();
+        rules.set_tracking_protection(&tracking);
+        
+        assert!(matches!(rules.load_extension(&extension(vec![])), Err(ExtensionError::PermissionDenied)));
+        let ext = extension(vec![Permission::DeclarativeNetRequest, Permission::Host("*://*.example.com/*".to_string())]);
+        assert_eq!(rules.load_extension(&ext).unwrap(), 3);
+        
+        let script = |url: &str| Request::get(url).with_resource_type(fos_net::ResourceType::Script);
+        assert_eq!(rules.intercept_request(&mut script("https://ads.example/a.js")), Interception::Block);
+        assert_eq!(rules.intercept_request(&mut script("https://doubleclick.net/a.js")), Interception::Block);
+        // No host permission for cdn.example, so no redirect
+        assert_eq!(rules.intercept_request(&mut script("https://cdn.example/track.js")), Interception::Continue);
+        
+        let mut request = script("https://www.example.com/app.js").with_header("referer", "https://a.test/");
+        assert_eq!(rules.intercept_request(&mut request), Interception::Continue);
+        assert!(request.headers.is_empty());
+        let mut headers = vec![("x-frame-options".to_string(), "SAMEORIGIN".to_string())];
+        rules.intercept_response(&request, &mut headers);
+        assert_eq!(headers, vec![("x-frame-options".to_string(), "DENY".to_string())]);
+        
+        rules.unload_extension("blocker");
+        assert_eq!(rules.rule_count("blocker"), 0);
+        assert_eq!(rules.intercept_request(&mut script("https://ads.example/a.js")), Interception::Continue);
+    }
+}
@@ -4,11 +4,26 @@
 
 use std::collections::HashMap;
 use fos_dom::{Document, DomTree, NodeId};
+use fos_css::{StyleResolver, RuleOrigin};
+use fos_layout::{BoxDimensions, EdgeSizes, Rect};
+use fos_render::{Canvas, HighlightOverlay};
 use fos_devtools::{
     Console, ConsoleMessage,
-    Inspector, InspectedNode,
+    Inspector, InspectedNode, DomEdit, BoxModel,
     NetworkPanel, NetworkRequest,
+    MemoryPanel,
+    CoveragePanel, CoverageEntry, CoverageRange, CoverageType,
+    AccessibilityPanel, AxNode, AxIssue, AxIssueSeverity,
 };
+use fos_a11y::{A11yIssue, AccessibilityAudit, AccessibilityNode, AriaRole, AriaState, IssueSeverity, compute_text_alternative};
+use fos_devtools::memory::{AllocationSample, HeapEdge, HeapEdgeType, HeapNode, HeapNodeType, StackFrame};
+use fos_js::engine::{EdgeName, HeapNodeId, HeapNodeKind, HeapSnapshot};
+use fos_net::RequestTimings;
+use fos_devtools::TimingBreakdown;
+use crate::accessibility::AccessibilityManager;
+use crate::js_runtime::PageJsRuntime;
+use crate::profiling::PerformanceProfiler;
+use fos_devtools::inspector::{InspectedStyleRule, StyleProperty, StyleSource};
 
 /// DevTools manager for the browser
 pub struct DevTools {
@@ -18,6 +33,17 @@ pub struct DevTools {
     pub inspector: Inspector,
     /// Network panel
     pub network: NetworkPanel,
+    /// Performance panel recording
+    pub profiler: PerformanceProfiler,
+    /// Heap snapshots and allocation samples
+    pub memory: MemoryPanel,
+    /// Used and unused CSS and JavaScript
+    pub coverage: CoveragePanel,
+    /// Accessibility tree with audit findings
+    pub accessibility: AccessibilityPanel,
+    /// Per-tab sensor, geolocation and permission emulation
+    #[cfg(feature = "full")]
+    pub emulation: crate::emulation::EmulationManager,
     /// Whether DevTools is open
     is_open: bool,
     /// Active panel
@@ -32,6 +58,9 @@ pub enum DevToolsPanel {
     Network,
     Sources,
     Performance,
+    Memory,
+    Coverage,
+    Accessibility,
 }
 
 impl Default for DevToolsPanel {
@@ -47,6 +76,12 @@ impl DevTools {
             console: Console::new(),
             inspector: Inspector::new(),
             network: NetworkPanel::new(),
+            profiler: PerformanceProfiler::new(),
+            memory: MemoryPanel::new(),
+            coverage: CoveragePanel::new(),
+            accessibility: AccessibilityPanel::new(),
+            #[cfg(feature = "full")]
+            emulation: crate::emulation::EmulationManager::new(),
             is_open: false,
             active_panel: DevToolsPanel::Console,
         }
@@ -182,6 +217,101 @@ impl DevTools {
         self.inspector.get_dom_tree(root_id, depth)
     }
     
+    /// Fill the matched-rule and computed panels of a node from the cascade
+    pub fn inspect_styles(&mut self, resolver: &StyleResolver, tree: &DomTree, node_id: NodeId) {
+        let id = node_id.index() as u64;
+        let trace = resolver.trace_style(tree, node_id);
+        
+        let mut computed = HashMap::new();
+        let rules = trace.rules.iter().map(|rule| {
+            let properties = rule.declarations.iter().map(|d| {
+                let (name, value) = (d.declaration.property.name(), d.declaration.value.to_css());
+                if !d.overridden {
+                    computed.insert(name.clone(), value.clone());
+                }
+                StyleProperty { name, value, priority: d.declaration.important, overridden: d.overridden }
+            }).collect();
+            InspectedStyleRule {
+                selector: rule.selector.clone(),
+                source: StyleSource {
+                    url: None,
+                    user_agent: rule.origin == RuleOrigin::UserAgent,
+                    line: 0,
+                    column: 0,
+                    is_inline: false,
+                },
+                properties,
+            }
+        }).collect();
+        
+        self.inspector.set_matching_rules(id, rules);
+        self.inspector.set_computed_styles(id, computed);
+    }
+    
+    /// Apply edits made in the inspector to the live DOM and the forced
+    /// pseudo-classes to the style resolver. Returns whether anything
+    /// changed, in which case styles and layout must be recomputed.
+    pub fn apply_inspector_edits(&mut self, tree: &mut DomTree, resolver: &mut StyleResolver) -> bool {
+        let edits = self.inspector.take_edits();
+        for edit in &edits {
+            match edit {
+                DomEdit::SetAttribute { node, name, value } => tree.set_attribute(dom_node(*node), name, value),
+                DomEdit::RemoveAttribute { node, name } => tree.remove_attribute(dom_node(*node), name),
+                DomEdit::SetText { node, text } => tree.set_text(dom_node(*node), text),
+                DomEdit::RemoveNode { node } => tree.remove(dom_node(*node)),
+                DomEdit::ForcePseudoState { node, states } => {
+                    let states: Vec<&str> = states.iter().map(String::as_str).collect();
+                    resolver.force_pseudo_state(dom_node(*node), &states);
+                }
+            }
+        }
+        !edits.is_empty()
+    }
+    
+    /// Record the layout box of an inspected node for the box model panel
+    /// and the highlight overlay
+    pub fn set_layout_box(&mut self, id: u64, dims: &BoxDimensions) {
+        let edges = |r: Rect| fos_devtools::elements::Rect {
+            top: r.y as f64,
+            right: r.right() as f64,
+            bottom: r.bottom() as f64,
+            left: r.x as f64,
+        };
+        let border = dims.border_box();
+        self.inspector.set_box_model(id, BoxModel {
+            content: edges(dims.content_box()),
+            padding: edges(dims.padding_box()),
+            border: edges(border),
+            margin: edges(dims.margin_box()),
+            width: border.width as f64,
+            height: border.height as f64,
+        });
+    }
+    
+    /// Paint the box model of the highlighted node over the page
+    pub fn paint_highlight(&self, canvas: &mut Canvas) {
+        let Some((_, model)) = self.inspector.highlighted() else { return };
+        let inset = |outer: &fos_devtools::elements::Rect, inner: &fos_devtools::elements::Rect| EdgeSizes {
+            top: (inner.top - outer.top) as f32,
+            right: (outer.right - inner.right) as f32,
+            bottom: (outer.bottom - inner.bottom) as f32,
+            left: (inner.left - outer.left) as f32,
+        };
+        let content = &model.content;
+        let dims = BoxDimensions {
+            content: Rect::new(
+                content.left as f32,
+                content.top as f32,
+                (content.right - content.left) as f32,
+                (content.bottom - content.top) as f32,
+            ),
+            padding: inset(&model.padding, &model.content),
+            border: inset(&model.border, &model.padding),
+            margin: inset(&model.margin, &model.border),
+        };
+        HighlightOverlay::default().paint(canvas, &dims);
+    }
+    
     // === Network Methods ===
     
     /// Log a network request start
@@ -194,6 +324,33 @@ impl DevTools {
         self.network.log_response(request_id, status, status_text, HashMap::new());
     }
     
+    /// Log a finished fetch with its headers, body and phase timings
+    pub fn log_fetch_response(
+        &mut self,
+        request_id: u64,
+        status: u16,
+        headers: &[(String, String)],
+        body: &[u8],
+        timings: Option<RequestTimings>,
+    ) {
+        let headers: HashMap<String, String> = headers.iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), value.clone()))
+            .collect();
+        self.network.log_response(request_id, status, status_text(status), headers);
+        self.network.set_response_body(request_id, body.to_vec());
+        if let Some(t) = timings {
+            self.network.set_timing(request_id, TimingBreakdown {
+                blocked: None,
+                dns: t.dns_ms,
+                connect: t.connect_ms,
+                ssl: t.tls_ms,
+                send: t.send_ms,
+                wait: t.wait_ms,
+                receive: t.receive_ms,
+            });
+        }
+    }
+    
     /// Log a network error
     pub fn log_network_error(&mut self, request_id: u64, error: &str) {
         self.network.log_error(request_id, error);
@@ -228,6 +385,301 @@ impl DevTools {
             total_bytes: total_size,
         }
     }
+    
+    // === Performance Methods ===
+    
+    /// Start a performance recording, or stop the running one and return
+    /// it as Chrome trace-event JSON
+    pub fn toggle_recording(&mut self) -> Option<String> {
+        if self.profiler.is_active() {
+            self.profiler.stop();
+            let timeline = self.profiler.frame_timeline();
+            let long = timeline.long_frames().count();
+            self.log(&format!("Recorded {} frames ({} long)", timeline.frames.len(), long));
+            Some(self.profiler.export_trace())
+        } else {
+            self.profiler.start();
+            self.set_panel(DevToolsPanel::Performance);
+            None
+        }
+    }
+    
+    // === Memory Methods ===
+    
+    /// Snapshot the page's JS heap, logging the constructors that grew
+    /// since the previous snapshot
+    pub fn take_heap_snapshot(&mut self, runtime: &PageJsRuntime) -> Option<u64> {
+        let snapshot = runtime.heap_snapshot()?;
+        let previous = self.memory.get_snapshots().last().map(|s| s.id);
+        let (nodes, edges) = convert_heap_snapshot(&snapshot);
+        let id = self.memory.add_snapshot(nodes, edges);
+        self.log(&format!("Heap snapshot {}: {} objects, {} bytes",
+            id, snapshot.nodes().len(), snapshot.total_size()));
+        
+        if let Some(previous) = previous {
+            let comparison = self.memory.compare_snapshots(previous, id);
+            for leak in comparison.leak_candidates.iter().take(3) {
+                let site = leak.allocation_site.as_ref()
+                    .map(|frame| format!(" (allocated in {})", frame.function_name))
+                    .unwrap_or_default();
+                self.warn(&format!("{} +{} objects, {:+} bytes{}", leak.constructor, leak.count_delta, leak.size_delta, site));
+            }
+        }
+        self.set_panel(DevToolsPanel::Memory);
+        Some(id)
+    }
+    
+    /// Start sampling JS allocations, or stop and record the samples
+    pub fn toggle_allocation_sampling(&mut self, runtime: &PageJsRuntime) {
+        if !self.memory.is_recording() {
+            self.memory.start_recording();
+            runtime.start_allocation_sampling(fos_js::engine::AllocationSampler::DEFAULT_INTERVAL);
+            self.set_panel(DevToolsPanel::Memory);
+            return;
+        }
+        
+        if let Some(sampler) = runtime.stop_allocation_sampling() {
+            let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default().as_millis() as u64;
+            for sample in sampler.samples() {
+                let stack_trace = sample.stack.iter().map(|name| StackFrame {
+                    function_name: name.to_string(), script_id: 0, url: String::new(), line: 0, column: 0,
+                }).collect();
+                self.memory.record_allocation(AllocationSample {
+                    timestamp, size: sample.size, node_id: heap_node_id(sample.node), stack_trace,
+                });
+            }
+            if let Some(site) = sampler.profile().first() {
+                let stack = site.stack.last().map_or("(top level)", |name| name);
+                self.log(&format!("Most allocations: {} (~{} bytes)", stack, site.bytes));
+            }
+        }
+        self.memory.stop_recording();
+    }
+    
+    // === Coverage Methods ===
+    
+    /// Start recording which author CSS rules match and which scripts'
+    /// functions run
+    pub fn start_coverage(&mut self, resolver: &mut StyleResolver, runtime: &PageJsRuntime) {
+        self.coverage.start(CoverageType::Css);
+        self.coverage.start(CoverageType::Js);
+        resolver.start_rule_usage_tracking();
+        runtime.start_coverage();
+        self.set_panel(DevToolsPanel::Coverage);
+    }
+    
+    /// Stop recording and fill the coverage panel; `stylesheets` gives the
+    /// URL and text length of each author stylesheet, in insertion order
+    pub fn stop_coverage(&mut self, resolver: &mut StyleResolver, runtime: &PageJsRuntime, stylesheets: &[(&str, usize)]) {
+        let mut sheets: Vec<CoverageEntry> = stylesheets.iter()
+            .map(|(url, len)| CoverageEntry::new(url, CoverageType::Css, *len))
+            .collect();
+        for usage in resolver.stop_rule_usage_tracking() {
+            if let Some(sheet) = sheets.get_mut(usage.stylesheet) {
+                sheet.ranges.push(CoverageRange { name: String::new(), start: usage.range.start, end: usage.range.end, count: usage.used as u64 });
+            }
+        }
+        for sheet in sheets {
+            self.coverage.record(sheet);
+        }
+        
+        for script in runtime.stop_coverage() {
+            let mut entry = CoverageEntry::new(&script.url, CoverageType::Js, script.source_len as usize);
+            for function in &script.functions {
+                entry = entry.with_range(&function.name, function.start as usize..function.end as usize, function.count);
+            }
+            self.coverage.record(entry);
+        }
+        self.coverage.stop(CoverageType::Css);
+        self.coverage.stop(CoverageType::Js);
+        
+        let (total, unused) = self.coverage.totals();
+        self.log(&format!("Coverage: {} of {} bytes unused", unused, total));
+    }
+    
+    // === Accessibility Methods ===
+    
+    /// Show the page's accessibility tree beside the DOM inspector, with
+    /// the audit's findings on the nodes they concern
+    pub fn inspect_accessibility(&mut self, manager: &AccessibilityManager) {
+        let tree = &manager.tree;
+        let Some(root) = tree.root_id() else {
+            self.accessibility.clear();
+            return;
+        };
+        
+        let mut nodes = Vec::new();
+        let mut audit = AccessibilityAudit::new();
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            let Some(node) = tree.get_node(id) else { continue };
+            stack.extend(node.children.iter().rev());
+            
+            let name = compute_text_alternative(node);
+            if !node.aria.is_hidden() {
+                if node.role == AriaRole::Img {
+                    audit.check_image(id, !name.is_empty(), &name);
+                }
+                audit.check_label(id, node.role, !name.is_empty());
+                if node.role == AriaRole::Link && is_vague_link_text(&name) {
+                    audit.add_issue(A11yIssue::VagueLink { element_id: id, text: name.clone() });
+                }
+            }
+            
+            let mut ax = AxNode::new(id, &format!("{:?}", node.role).to_lowercase());
+            ax.dom_node = manager.dom_node(id).map(|n| n.index() as u64);
+            ax.name = name;
+            ax.description = node.description.clone();
+            ax.value = node.value.clone();
+            ax.states = ax_states(node);
+            ax.ignored = node.aria.is_hidden();
+            ax.parent = node.parent;
+            ax.children = node.children.clone();
+            nodes.push(ax);
+        }
+        self.accessibility.set_tree(root, nodes);
+        
+        for issue in &audit.issues {
+            let (element, summary) = issue_target(issue);
+            self.accessibility.add_issue(element.unwrap_or(root), AxIssue {
+                severity: match issue.severity() {
+                    IssueSeverity::Info => AxIssueSeverity::Info,
+                    IssueSeverity::Warning => AxIssueSeverity::Warning,
+                    IssueSeverity::Error => AxIssueSeverity::Error,
+                    IssueSeverity::Critical => AxIssueSeverity::Critical,
+                },
+                message: format!("{}. {}", summary, audit.suggest_fix(issue).description),
+                wcag: issue.wcag_criteria().to_string(),
+            });
+        }
+        self.set_panel(DevToolsPanel::Accessibility);
+        self.log(&format!("Accessibility: {} issues", audit.issues.len()));
+    }
+}
+
+/// Computed states of an accessibility node, as the accessibility panel
+/// lists them
+fn ax_states(node: &AccessibilityNode) -> Vec<String> {
+    let mut states = Vec::new();
+    if node.focusable {
+        states.push("focusable");
+    }
+    if node.focused {
+        states.push("focused");
+    }
+    if node.aria.is_disabled() {
+        states.push("disabled");
+    }
+    match node.aria.is_expanded() {
+        Some(true) => states.push("expanded"),
+        Some(false) => states.push("collapsed"),
+        None => {}
+    }
+    let on = |key: &str| match node.aria.states.get(key) {
+        Some(AriaState::Checked(checked) | AriaState::Pressed(checked)) => checked.unwrap_or(true),
+        Some(AriaState::Selected(on) | AriaState::Required(on) | AriaState::Invalid(on) | AriaState::ReadOnly(on)) => *on,
+        _ => false,
+    };
+    for (key, state) in [("checked", "checked"), ("pressed", "pressed"), ("selected", "selected"),
+        ("required", "required"), ("invalid", "invalid"), ("readonly", "readonly")] {
+        if on(key) {
+            states.push(state);
+        }
+    }
+    states.into_iter().map(String::from).collect()
+}
+
+/// Link text that says nothing about where the link goes
+fn is_vague_link_text(text: &str) -> bool {
+    matches!(text.trim().to_lowercase().as_str(), "click here" | "here" | "more" | "read more" | "link" | "this")
+}
+
+/// Accessibility node an audit finding is about, `None` for the whole
+/// page, and what is wrong
+fn issue_target(issue: &A11yIssue) -> (Option<u64>, &'static str) {
+    match issue {
+        A11yIssue::MissingAltText { element_id } => (Some(*element_id), "Image has no text alternative"),
+        A11yIssue::MissingLabel { element_id, .. } => (Some(*element_id), "Control has no accessible name"),
+        A11yIssue::LowContrast { element_id, .. } => (Some(*element_id), "Text contrast is too low"),
+        A11yIssue::HeadingSkip { element_id, .. } => (Some(*element_id), "Heading level is skipped"),
+        A11yIssue::FocusNotVisible { element_id } => (Some(*element_id), "Focus is not visible"),
+        A11yIssue::SmallTouchTarget { element_id, .. } => (Some(*element_id), "Touch target is too small"),
+        A11yIssue::MissingLang => (None, "Page has no language"),
+        A11yIssue::MissingTitle => (None, "Page has no title"),
+        A11yIssue::FormMissingLabel { element_id, .. } => (Some(*element_id), "Form field has no label"),
+        A11yIssue::VagueLink { element_id, .. } => (Some(*element_id), "Link text does not describe its target"),
+        A11yIssue::AutoPlayMedia { element_id } => (Some(*element_id), "Media plays automatically"),
+    }
+}
+
+/// Memory panel node ID of a heap cell; 0 is the synthetic GC root
+fn heap_node_id(id: HeapNodeId) -> u64 {
+    ((id.kind as u64 + 1) << 32) | id.index as u64
+}
+
+/// Convert a JS heap snapshot into Memory panel nodes and edges
+fn convert_heap_snapshot(snapshot: &HeapSnapshot) -> (Vec<HeapNode>, Vec<HeapEdge>) {
+    let nodes = snapshot.nodes().iter().map(|node| HeapNode {
+        id: heap_node_id(node.id),
+        node_type: match node.id.kind {
+            HeapNodeKind::Object => HeapNodeType::Object,
+            HeapNodeKind::Array => HeapNodeType::Array,
+            HeapNodeKind::Closure => HeapNodeType::Closure,
+        },
+        name: node.name.to_string(),
+        size: node.self_size,
+        retained_size: node.retained_size,
+        edge_count: snapshot.edges().iter().filter(|e| e.from == Some(node.id)).count(),
+    }).collect();
+    
+    let edges = snapshot.edges().iter().map(|edge| {
+        let (edge_type, name_or_index) = match &edge.name {
+            EdgeName::Property(name) | EdgeName::Global(name) => (HeapEdgeType::Property, name.to_string()),
+            EdgeName::Element(i) => (HeapEdgeType::Element, i.to_string()),
+            EdgeName::Prototype => (HeapEdgeType::Internal, "__proto__".to_string()),
+            EdgeName::Upvalue(i) => (HeapEdgeType::Context, i.to_string()),
+            EdgeName::Stack(i) => (HeapEdgeType::Hidden, i.to_string()),
+            EdgeName::This => (HeapEdgeType::Hidden, "this".to_string()),
+        };
+        HeapEdge {
+            edge_type,
+            name_or_index,
+            from_node: edge.from.map_or(0, heap_node_id),
+            to_node: heap_node_id(edge.to),
+        }
+    }).collect();
+    
+    (nodes, edges)
+}
+
+/// Reason phrase of common status codes
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        203 => "Non-Authoritative Information",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+/// DOM node behind an inspector node ID
+fn dom_node(id: u64) -> NodeId {
+    NodeId(id as u32)
 }
 
 impl Default for DevTools {
@@ -284,4 +736,141 @@ mod tests {
         let stats = devtools.get_network_stats();
         assert_eq!(stats.total_requests, 1);
     }
+    
+    #[test]
+    fn test_fetch_logging() {
+        let mut devtools = DevTools::new();
+        let id = devtools.log_request("https://example.com/", "GET");
+        let headers = [("Content-Type".to_string(), "text/html".to_string())];
+        let timings = RequestTimings { dns_ms: Some(3.0), connect_ms: Some(7.0), send_ms: 1.0, wait_ms: 20.0, receive_ms: 4.0, ..Default::default() };
+        devtools.log_fetch_response(id, 200, &headers, b"<p>hi</p>", Some(timings));
+        
+        let response = devtools.network.get_response(id).unwrap();
+        assert_eq!(response.content_type.as_deref(), Some("text/html"));
+        assert_eq!(response.body.as_deref(), Some(&b"<p>hi</p>"[..]));
+        assert_eq!(devtools.get_network_requests()[0].phases.unwrap().ttfb(), 31.0);
+        assert!(devtools.network.export_har().contains("\"connect\":7"));
+    }
+    
+    #[test]
+    fn test_performance_recording() {
+        let mut devtools = DevTools::new();
+        assert!(devtools.toggle_recording().is_none());
+        assert_eq!(devtools.active_panel(), DevToolsPanel::Performance);
+        
+        devtools.profiler.begin_frame();
+        devtools.profiler.end_frame();
+        let trace = devtools.toggle_recording().unwrap();
+        assert!(trace.contains("\"BeginFrame\""));
+        assert!(!devtools.profiler.is_active());
+    }
+    
+    #[test]
+    fn test_heap_snapshot_diff() {
+        let mut runtime = PageJsRuntime::new("about:blank");
+        runtime.initialize(std::sync::Arc::new(std::sync::Mutex::new(Document::new("about:blank")))).unwrap();
+        let mut devtools = DevTools::new();
+        let before = devtools.take_heap_snapshot(&runtime).unwrap();
+        
+        devtools.toggle_allocation_sampling(&runtime);
+        runtime.eval("let rows = [{}, {}, {}]; rows;").unwrap();
+        devtools.toggle_allocation_sampling(&runtime);
+        let after = devtools.take_heap_snapshot(&runtime).unwrap();
+        assert_eq!(devtools.active_panel(), DevToolsPanel::Memory);
+        
+        let comparison = devtools.memory.compare_snapshots(before, after);
+        let rows = comparison.leak_candidates.iter().find(|c| c.constructor == "Object").unwrap();
+        assert_eq!(rows.count_delta, 3);
+        assert!(!devtools.memory.is_recording());
+    }
+    
+    #[test]
+    fn test_coverage_recording() {
+        let mut runtime = PageJsRuntime::new("about:blank");
+        runtime.initialize(std::sync::Arc::new(std::sync::Mutex::new(Document::new("about:blank")))).unwrap();
+        let mut tree = DomTree::new();
+        let div = tree.create_element("div");
+        tree.append_child(tree.root(), div);
+        let css = "div { color: red }";
+        let mut resolver = StyleResolver::new();
+        resolver.add_stylesheet(fos_css::parse_stylesheet(css).unwrap());
+        
+        let mut devtools = DevTools::new();
+        devtools.start_coverage(&mut resolver, &runtime);
+        resolver.compute_style(&tree, div);
+        let script = "function used() {} function unused() { return 1; } used();";
+        runtime.execute_external_script("https://example.com/app.js", script).unwrap();
+        devtools.stop_coverage(&mut resolver, &runtime, &[("https://example.com/app.css", css.len())]);
+        assert_eq!(devtools.active_panel(), DevToolsPanel::Coverage);
+        
+        let report = devtools.coverage.report();
+        let js = report.iter().find(|r| r.url == "https://example.com/app.js").unwrap();
+        assert_eq!(js.unused_bytes, "function unused() { return 1; }".len());
+        let sheet = report.iter().find(|r| r.url == "https://example.com/app.css").unwrap();
+        assert_eq!(sheet.unused_bytes, 0);
+    }
+    
+    #[test]
+    fn test_accessibility_inspection() {
+        let mut document = Document::new("about:blank");
+        let body = document.body();
+        let tree = document.tree_mut();
+        let heading = tree.create_element("h1");
+        let title = tree.create_text("Welcome");
+        tree.append_child(heading, title);
+        tree.append_child(body, heading);
+        let menu = tree.create_element("button");
+        tree.set_attribute(menu, "aria-expanded", "false");
+        let label = tree.create_text("Menu");
+        tree.append_child(menu, label);
+        tree.append_child(body, menu);
+        let image = tree.create_element("img");
+        tree.append_child(body, image);
+        let hidden = tree.create_element("div");
+        tree.set_attribute(hidden, "aria-hidden", "true");
+        let close = tree.create_element("button");
+        tree.set_attribute(close, "aria-label", "Close");
+        tree.append_child(hidden, close);
+        tree.append_child(body, hidden);
+        
+        let mut manager = AccessibilityManager::new();
+        manager.build_from_document(&document);
+        let mut devtools = DevTools::new();
+        devtools.inspect_accessibility(&manager);
+        assert_eq!(devtools.active_panel(), DevToolsPanel::Accessibility);
+        
+        let button = devtools.accessibility.node_for_dom(menu.index() as u64).unwrap();
+        assert_eq!(button.states, vec!["focusable", "collapsed"]);
+        let issues = devtools.accessibility.issues();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].0.dom_node, Some(image.index() as u64));
+        assert_eq!(issues[0].1.wcag, "1.1.1 Non-text Content");
+        
+        assert_eq!(devtools.accessibility.reader_next().as_deref(), Some("Welcome, heading"));
+        assert_eq!(devtools.accessibility.reader_next().as_deref(), Some("Menu, button, collapsed"));
+        assert_eq!(devtools.accessibility.reader_next().as_deref(), Some("img"));
+        assert_eq!(devtools.accessibility.reader_next(), None);
+    }
+    
+    #[test]
+    fn test_inspector_edits_reach_dom() {
+        let mut tree = DomTree::new();
+        let div = tree.create_element("div");
+        tree.append_child(tree.root(), div);
+        let mut resolver = StyleResolver::new();
+        
+        let mut devtools = DevTools::new();
+        devtools.inspector.add_node(InspectedNode::element(div.index() as u64, "div"));
+        devtools.inspect_styles(&resolver, &tree, div);
+        let rules = devtools.inspector.get_matching_rules(div.index() as u64);
+        assert!(rules[0].source.user_agent);
+        assert_eq!(devtools.inspector.get_computed_style(div.index() as u64, "display"), Some("block"));
+        
+        devtools.inspector.set_attribute(div.index() as u64, "id", "main");
+        devtools.inspector.force_pseudo_state(div.index() as u64, &["hover"]);
+        assert!(devtools.apply_inspector_edits(&mut tree, &mut resolver));
+        assert!(tree.get(div).unwrap().as_element().unwrap().id.is_some());
+        assert_eq!(resolver.forced_pseudo_state(div), ["hover".to_string()]);
+        assert!(!devtools.apply_inspector_edits(&mut tree, &mut resolver));
+    }
 }
@@ -1,8 +1,14 @@
 //! Drag and Drop API
 //!
-//! HTML5 drag and drop functionality.
+//! HTML5 drag and drop functionality. Besides drags within the page, files
+//! dragged in from the OS are delivered to the element under the pointer
+//! as `dragenter`/`dragover`/`drop` with `dataTransfer.files`, and links
+//! and images can be dragged out to other applications.
 
 use std::collections::HashMap;
+use std::path::PathBuf;
+
+use fos_js::webapi::blob::{File, mime_type_for_name};
 
 /// Drag operation type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -72,6 +78,49 @@ impl DataTransfer {
         self.data.keys().map(|s| s.as_str()).collect()
     }
     
+    /// Data for dragging a link out: its URL in the forms other
+    /// applications look for, and the link as HTML
+    pub fn for_link(url: &str, text: &str) -> Self {
+        let text = if text.trim().is_empty() { url } else { text.trim() };
+        let mut data = Self::new();
+        data.set_data("text/uri-list", url);
+        data.set_data("text/plain", url);
+        data.set_data("text/x-moz-url", &format!("{}\n{}", url, text));
+        data.set_data("text/html", &format!("<a href=\"{}\">{}</a>", escape_html(url), escape_html(text)));
+        data.effect_allowed = DragEffectAllowed::CopyLink;
+        data
+    }
+    
+    /// Data for dragging an image out. `DownloadURL` lets file managers
+    /// save the image itself; `cached` is the image file, when on disk.
+    pub fn for_image(src: &str, alt: &str, cached: Option<PathBuf>) -> Self {
+        let name = src.split(['?', '#']).next().unwrap_or(src)
+            .rsplit('/').next()
+            .filter(|name| !name.is_empty())
+            .unwrap_or("image");
+        let mime_type = match mime_type_for_name(name) {
+            "" => "application/octet-stream",
+            mime_type => mime_type,
+        };
+        
+        let mut data = Self::new();
+        data.set_data("text/uri-list", src);
+        data.set_data("text/plain", src);
+        data.set_data("text/html", &format!("<img src=\"{}\" alt=\"{}\">", escape_html(src), escape_html(alt)));
+        data.set_data("DownloadURL", &format!("{}:{}:{}", mime_type, name, src));
+        if let Some(path) = cached {
+            let size = std::fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+            data.add_file(DragFile {
+                name: name.to_string(),
+                size,
+                mime_type: mime_type.to_string(),
+                path: Some(path.to_string_lossy().into_owned()),
+            });
+        }
+        data.effect_allowed = DragEffectAllowed::CopyLink;
+        data
+    }
+    
     /// Add a file
     pub fn add_file(&mut self, file: DragFile) {
         self.files.push(file);
@@ -113,6 +162,71 @@ pub enum DragEventType {
     DragEnd,
 }
 
+/// Element ID drag events go to when they target the document itself
+pub const DOCUMENT_TARGET: u64 = 0;
+
+/// A drag event for the page and the element it is dispatched to
+#[derive(Debug, Clone)]
+pub struct PageDragEvent {
+    pub target: u64,
+    pub event: fos_js::DragEvent,
+}
+
+impl PageDragEvent {
+    fn new(target: u64, event_type: fos_js::DragEventType, x: f32, y: f32, data: fos_js::DataTransfer) -> Self {
+        Self { target, event: fos_js::DragEvent::new(event_type, x as f64, y as f64, data) }
+    }
+}
+
+/// Starts drags that leave the window, implemented by the app layer on
+/// platforms whose windowing supports it
+pub trait OsDragSource: Send + std::fmt::Debug {
+    /// Begin an OS drag carrying `data`; whether one started
+    fn start_drag(&mut self, data: &DataTransfer) -> bool;
+}
+
+/// Files the OS is dragging over the page
+#[derive(Debug, Default)]
+struct OsDrag {
+    /// Files announced while hovering
+    hovered: Vec<PathBuf>,
+    /// Files dropped so far; the OS reports them one at a time
+    dropped: Vec<PathBuf>,
+    /// Element under the pointer, which was sent `dragenter`
+    target: Option<u64>,
+    x: f32,
+    y: f32,
+}
+
+impl OsDrag {
+    /// What the page sees while files are dragged over it: their types,
+    /// but not their contents until they are dropped
+    fn hover_data(&self) -> fos_js::DataTransfer {
+        let mut data = fos_js::DataTransfer::new();
+        for path in &self.hovered {
+            let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+            data.add_file_item(mime_type_for_name(&name));
+        }
+        data.effect_allowed = fos_js::events::EffectAllowed::Copy;
+        data.drop_effect = fos_js::events::DropEffect::Copy;
+        data
+    }
+    
+    /// Move to the element under the pointer, leaving the one before
+    fn retarget(&mut self, target: Option<u64>, events: &mut Vec<PageDragEvent>) {
+        if target == self.target {
+            return;
+        }
+        if let Some(old) = self.target {
+            events.push(PageDragEvent::new(old, fos_js::DragEventType::DragLeave, self.x, self.y, self.hover_data()));
+        }
+        if let Some(new) = target {
+            events.push(PageDragEvent::new(new, fos_js::DragEventType::DragEnter, self.x, self.y, self.hover_data()));
+        }
+        self.target = target;
+    }
+}
+
 /// Drag and drop manager
 #[derive(Debug)]
 pub struct DragDropManager {
@@ -120,6 +234,10 @@ pub struct DragDropManager {
     active_drag: Option<ActiveDrag>,
     /// Drop targets (element IDs)
     drop_targets: Vec<u64>,
+    /// Files dragged in from the OS
+    os_drag: Option<OsDrag>,
+    /// Hands drags leaving the window to the OS
+    os_source: Option<Box<dyn OsDragSource>>,
 }
 
 /// Active drag state
@@ -142,9 +260,15 @@ impl DragDropManager {
         Self {
             active_drag: None,
             drop_targets: Vec::new(),
+            os_drag: None,
+            os_source: None,
         }
     }
     
+    pub fn set_os_source(&mut self, source: Box<dyn OsDragSource>) {
+        self.os_source = Some(source);
+    }
+    
     /// Start a drag operation
     pub fn start_drag(&mut self, element_id: u64, x: f32, y: f32, data: DataTransfer) {
         self.active_drag = Some(ActiveDrag {
@@ -219,6 +343,96 @@ impl DragDropManager {
     pub fn get_data_transfer(&self) -> Option<&DataTransfer> {
         self.active_drag.as_ref().map(|d| &d.data_transfer)
     }
+    
+    /// The active drag left the window: hand it to the OS so it can be
+    /// dropped in other applications. Returns whether the OS took it.
+    pub fn drag_out(&mut self) -> bool {
+        let (Some(drag), Some(source)) = (&self.active_drag, &mut self.os_source) else {
+            return false;
+        };
+        if !source.start_drag(&drag.data_transfer) {
+            return false;
+        }
+        self.active_drag = None;
+        self.drop_targets.clear();
+        true
+    }
+    
+    /// Whether the OS is dragging files over the window
+    pub fn is_os_dragging(&self) -> bool {
+        self.os_drag.is_some()
+    }
+    
+    /// The OS started dragging `path` over the window
+    pub fn os_file_hovered(&mut self, path: PathBuf) {
+        self.os_drag.get_or_insert_with(OsDrag::default).hovered.push(path);
+    }
+    
+    /// The pointer moved while the OS drags files; `hit_test` finds the
+    /// element under a point
+    pub fn os_drag_over(&mut self, x: f32, y: f32, hit_test: impl Fn(f32, f32) -> Option<u64>) -> Vec<PageDragEvent> {
+        let Some(drag) = &mut self.os_drag else { return Vec::new() };
+        drag.x = x;
+        drag.y = y;
+        
+        let mut events = Vec::new();
+        drag.retarget(hit_test(x, y), &mut events);
+        if let Some(target) = drag.target {
+            events.push(PageDragEvent::new(target, fos_js::DragEventType::DragOver, x, y, drag.hover_data()));
+        }
+        events
+    }
+    
+    /// The files were dragged away again or the drag was cancelled
+    pub fn os_drag_cancelled(&mut self) -> Vec<PageDragEvent> {
+        let mut events = Vec::new();
+        if let Some(mut drag) = self.os_drag.take() {
+            drag.retarget(None, &mut events);
+        }
+        events
+    }
+    
+    /// The OS dropped `path` on the window. Each file of a drop comes
+    /// separately; `finish_os_drop` delivers them together.
+    pub fn os_file_dropped(&mut self, path: PathBuf) {
+        self.os_drag.get_or_insert_with(OsDrag::default).dropped.push(path);
+    }
+    
+    /// Deliver the dropped files to the element under the pointer as
+    /// `File` objects in a `drop` event
+    pub fn finish_os_drop(&mut self, hit_test: impl Fn(f32, f32) -> Option<u64>) -> Vec<PageDragEvent> {
+        let Some(mut drag) = self.os_drag.take_if(|drag| !drag.dropped.is_empty()) else {
+            return Vec::new();
+        };
+        
+        // Drops report no position; the last one the pointer had stands
+        let mut events = Vec::new();
+        if drag.hovered.is_empty() {
+            drag.hovered = drag.dropped.clone();
+        }
+        drag.retarget(hit_test(drag.x, drag.y), &mut events);
+        let Some(target) = drag.target else { return events };
+        
+        let mut data = fos_js::DataTransfer::new();
+        for path in &drag.dropped {
+            match File::from_path(path) {
+                Ok(file) => data.add_file(file),
+                Err(e) => log::warn!("Can't read dropped file {}: {}", path.display(), e),
+            }
+        }
+        data.effect_allowed = fos_js::events::EffectAllowed::Copy;
+        data.drop_effect = fos_js::events::DropEffect::Copy;
+        events.push(PageDragEvent::new(target, fos_js::DragEventType::Drop, drag.x, drag.y, data));
+        events
+    }
+}
+
+/// Escape text for an HTML attribute or element
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
 
 #[cfg(test)]
@@ -239,4 +453,49 @@ mod tests {
         assert_eq!(event.event_type, DragEventType::Drop);
         assert_eq!(event.data_transfer.get_data("text/plain"), "Hello, World!");
     }
+    
+    #[test]
+    fn test_os_file_drop() {
+        let path = std::env::temp_dir().join(format!("fos-drop-test-{}.txt", std::process::id()));
+        std::fs::write(&path, "dropped").unwrap();
+        
+        let mut mgr = DragDropManager::new();
+        let hit_test = |x: f32, _y: f32| if x < 100.0 { Some(1) } else { Some(2) };
+        mgr.os_file_hovered(path.clone());
+        let events = mgr.os_drag_over(10.0, 10.0, hit_test);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.event_type, fos_js::DragEventType::DragEnter);
+        assert_eq!(events[0].event.data_transfer.types(), vec!["Files"]);
+        assert!(events[1].event.data_transfer.files.is_empty());
+        
+        let events = mgr.os_drag_over(150.0, 10.0, hit_test);
+        let kinds: Vec<_> = events.iter().map(|e| (e.target, e.event.event_type)).collect();
+        assert_eq!(kinds, vec![
+            (1, fos_js::DragEventType::DragLeave),
+            (2, fos_js::DragEventType::DragEnter),
+            (2, fos_js::DragEventType::DragOver),
+        ]);
+        
+        mgr.os_file_dropped(path.clone());
+        let events = mgr.finish_os_drop(hit_test);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].target, 2);
+        let files = &events[0].event.data_transfer.files;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].mime_type(), "text/plain");
+        assert_eq!(files[0].as_blob().text(), "dropped");
+        assert!(!mgr.is_os_dragging());
+    }
+    
+    #[test]
+    fn test_link_drag_data() {
+        let data = DataTransfer::for_link("https://example.com/?a=1&b=2", "Example");
+        assert_eq!(data.get_data("text/uri-list"), "https://example.com/?a=1&b=2");
+        assert_eq!(data.get_data("text/html"), "<a href=\"https://example.com/?a=1&amp;b=2\">Example</a>");
+        
+        let image = DataTransfer::for_image("https://example.com/img/cat.png?v=3", "A cat", None);
+        assert_eq!(image.get_data("DownloadURL"), "image/png:cat.png:https://example.com/img/cat.png?v=3");
+        assert!(image.files().is_empty());
+    }
 }
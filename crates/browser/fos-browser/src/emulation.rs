@@ -0,0 +1,239 @@
+//! DevTools Device Emulation
+//!
+//! Per-tab sensor, geolocation and permission overrides, so device-dependent
+//! pages can be tested on desktop. Fake readings are served through the same
+//! `SensorProvider` and `PositionProvider` traits as the platform backends,
+//! which are swapped out while a tab is emulated and restored afterwards.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::geolocation::{Coordinates, GeolocationError, GeolocationManager, PositionProvider};
+use crate::permissions::{PermissionName, PermissionState, PermissionsManager};
+use crate::sensors::{DeviceMotion, DeviceOrientation, SensorProvider, SensorsManager, Vec3};
+use crate::tab::TabId;
+
+/// Emulated sensor readings; `None` leaves a sensor without readings
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SensorOverrides {
+    pub accelerometer: Option<Vec3>,
+    pub gyroscope: Option<Vec3>,
+    pub magnetometer: Option<Vec3>,
+    /// Ambient light in lux
+    pub illuminance: Option<f64>,
+    pub orientation: Option<DeviceOrientation>,
+    pub motion: Option<DeviceMotion>,
+}
+
+/// Sensor provider serving DevTools overrides
+#[derive(Debug, Clone, Default)]
+pub struct EmulatedSensors {
+    overrides: Arc<Mutex<SensorOverrides>>,
+}
+
+impl EmulatedSensors {
+    pub fn set(&self, overrides: SensorOverrides) {
+        *self.overrides.lock().unwrap() = overrides;
+    }
+
+    pub fn get(&self) -> SensorOverrides {
+        *self.overrides.lock().unwrap()
+    }
+}
+
+impl SensorProvider for EmulatedSensors {
+    fn accelerometer(&mut self) -> Option<Vec3> { self.get().accelerometer }
+    fn gyroscope(&mut self) -> Option<Vec3> { self.get().gyroscope }
+    fn magnetometer(&mut self) -> Option<Vec3> { self.get().magnetometer }
+    fn illuminance(&mut self) -> Option<f64> { self.get().illuminance }
+    fn orientation(&mut self) -> Option<DeviceOrientation> { self.get().orientation }
+    fn motion(&mut self) -> Option<DeviceMotion> { self.get().motion }
+}
+
+/// Position provider serving a DevTools override; unavailable until set
+#[derive(Debug, Clone)]
+pub struct EmulatedPosition {
+    position: Arc<Mutex<Result<Coordinates, GeolocationError>>>,
+}
+
+impl Default for EmulatedPosition {
+    fn default() -> Self {
+        Self { position: Arc::new(Mutex::new(Err(GeolocationError::PositionUnavailable))) }
+    }
+}
+
+impl EmulatedPosition {
+    /// Set the position, or the error `getCurrentPosition()` should fail with
+    pub fn set(&self, position: Result<Coordinates, GeolocationError>) {
+        *self.position.lock().unwrap() = position;
+    }
+}
+
+impl PositionProvider for EmulatedPosition {
+    fn position(&mut self, _high_accuracy: bool) -> Result<Coordinates, GeolocationError> {
+        self.position.lock().unwrap().clone()
+    }
+}
+
+/// Emulation state of one tab
+#[derive(Debug, Default)]
+struct TabEmulation {
+    sensors: EmulatedSensors,
+    position: EmulatedPosition,
+    /// Platform providers swapped out while emulating
+    real_sensors: Option<Box<dyn SensorProvider>>,
+    real_position: Option<Box<dyn PositionProvider>>,
+}
+
+/// Device emulation manager
+#[derive(Debug, Default)]
+pub struct EmulationManager {
+    tabs: HashMap<TabId, TabEmulation>,
+}
+
+impl EmulationManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_emulating(&self, tab: TabId) -> bool {
+        self.tabs.contains_key(&tab)
+    }
+
+    /// Start emulating a tab: its sensors and geolocation read DevTools
+    /// overrides instead of the hardware
+    pub fn enable(&mut self, tab: TabId, sensors: &mut SensorsManager, geolocation: &mut GeolocationManager) {
+        if self.is_emulating(tab) {
+            return;
+        }
+        let emulation = TabEmulation {
+            real_sensors: sensors.take_provider(),
+            real_position: geolocation.take_provider(),
+            ..Default::default()
+        };
+        sensors.set_provider(Box::new(emulation.sensors.clone()));
+        geolocation.set_provider(Box::new(emulation.position.clone()));
+        self.tabs.insert(tab, emulation);
+        log::debug!("Device emulation enabled for tab {}", tab);
+    }
+
+    /// Stop emulating a tab, restoring platform providers and dropping its
+    /// permission overrides
+    pub fn disable(
+        &mut self,
+        tab: TabId,
+        sensors: &mut SensorsManager,
+        geolocation: &mut GeolocationManager,
+        permissions: &mut PermissionsManager,
+    ) {
+        permissions.clear_tab_overrides(tab);
+        let Some(emulation) = self.tabs.remove(&tab) else { return };
+        sensors.take_provider();
+        if let Some(provider) = emulation.real_sensors {
+            sensors.set_provider(provider);
+        }
+        geolocation.take_provider();
+        if let Some(provider) = emulation.real_position {
+            geolocation.set_provider(provider);
+        }
+        log::debug!("Device emulation disabled for tab {}", tab);
+    }
+
+    /// Replace the emulated sensor readings; false if the tab isn't emulated
+    pub fn set_sensor_overrides(&self, tab: TabId, overrides: SensorOverrides) -> bool {
+        self.tabs.get(&tab).map(|e| e.sensors.set(overrides)).is_some()
+    }
+
+    pub fn sensor_overrides(&self, tab: TabId) -> Option<SensorOverrides> {
+        self.tabs.get(&tab).map(|e| e.sensors.get())
+    }
+
+    /// Set the emulated position (or error); false if the tab isn't emulated
+    pub fn set_geolocation(&self, tab: TabId, position: Result<Coordinates, GeolocationError>) -> bool {
+        self.tabs.get(&tab).map(|e| e.position.set(position)).is_some()
+    }
+
+    /// Override a permission state in a tab; `None` removes the override
+    pub fn set_permission(
+        &self,
+        tab: TabId,
+        permissions: &mut PermissionsManager,
+        name: PermissionName,
+        state: Option<PermissionState>,
+    ) {
+        permissions.set_tab_override(tab, name, state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geolocation::GeolocationOptions;
+    use crate::permissions::PermissionDescriptor;
+
+    #[derive(Debug)]
+    struct Hardware;
+
+    impl SensorProvider for Hardware {
+        fn illuminance(&mut self) -> Option<f64> { Some(80.0) }
+    }
+
+    #[test]
+    fn test_sensor_emulation() {
+        let mut emulation = EmulationManager::new();
+        let mut sensors = SensorsManager::new();
+        let mut geo = GeolocationManager::new();
+        let mut permissions = PermissionsManager::new();
+        sensors.set_provider(Box::new(Hardware));
+        sensors.ambient_light.start();
+
+        emulation.enable(1, &mut sensors, &mut geo);
+        assert!(!emulation.set_sensor_overrides(2, SensorOverrides::default()));
+        emulation.set_sensor_overrides(1, SensorOverrides {
+            illuminance: Some(10_000.0),
+            orientation: Some(DeviceOrientation { alpha: 90.0, beta: 45.0, gamma: 0.0, absolute: false }),
+            ..Default::default()
+        });
+        sensors.poll(0.0);
+        assert_eq!(sensors.ambient_light.illuminance(), Some(10_000.0));
+        assert_eq!(sensors.device_orientation().unwrap().beta, 45.0);
+
+        emulation.disable(1, &mut sensors, &mut geo, &mut permissions);
+        sensors.poll(1.0);
+        assert_eq!(sensors.ambient_light.illuminance(), Some(80.0));
+    }
+
+    #[test]
+    fn test_geolocation_and_permissions() {
+        let mut emulation = EmulationManager::new();
+        let mut sensors = SensorsManager::new();
+        let mut geo = GeolocationManager::new();
+        let mut permissions = PermissionsManager::new();
+        geo.request_permission();
+
+        emulation.enable(3, &mut sensors, &mut geo);
+        assert!(matches!(
+            geo.get_current_position(GeolocationOptions::default()),
+            Err(GeolocationError::PositionUnavailable)
+        ));
+        emulation.set_geolocation(3, Ok(Coordinates {
+            latitude: 35.68,
+            longitude: 139.69,
+            accuracy: 10.0,
+            altitude: None,
+            altitude_accuracy: None,
+            heading: None,
+            speed: None,
+        }));
+        let position = geo.get_current_position(GeolocationOptions::default()).unwrap();
+        assert_eq!(position.coords.latitude, 35.68);
+
+        let desc = PermissionDescriptor::new(PermissionName::Geolocation);
+        emulation.set_permission(3, &mut permissions, PermissionName::Geolocation, Some(PermissionState::Denied));
+        assert_eq!(permissions.request_in_tab(3, "https://maps.example", &desc), PermissionState::Denied);
+
+        emulation.disable(3, &mut sensors, &mut geo, &mut permissions);
+        assert!(!emulation.is_emulating(3));
+        assert_eq!(permissions.query_in_tab(3, "https://maps.example", &desc).state, PermissionState::Prompt);
+    }
+}
@@ -0,0 +1,1057 @@
+//! Extension APIs
+//!
+//! The `chrome` object extension code runs against: `chrome.storage.local`
+//! and `chrome.storage.sync` over the browser's storage, `chrome.tabs`, and
+//! `chrome.runtime` messaging between content scripts, the background page
+//! and the popup, and `chrome.contextMenus` items in the page's context
+//! menu. What each context gets follows the manifest's permissions.
+//!
+//! A script cannot be re-entered while it runs, so callbacks, message
+//! deliveries and tab changes are queued by the API and carried out by
+//! [`ExtensionRuntime::dispatch`] once the calling script is done. A
+//! message listener answers by returning a value.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use fos_dom::Document;
+use fos_js::{Callback, HostApi, HostObject, HostValue, IntoJs, JsError, JsValue};
+use crate::extensions::{Extension, ExtensionManager, MatchPattern, Permission};
+use crate::js_runtime::PageJsRuntime;
+use crate::storage::StorageManager;
+use crate::tab::TabId;
+use crate::ui::context_menu::{ContextMenuAction, ContextMenuItem, HitTestResult, MediaKind, MenuContext};
+
+/// Rounds of queued work per dispatch; work queued by the last round's
+/// callbacks waits for the next dispatch
+const MAX_DISPATCH_ROUNDS: usize = 8;
+
+/// Where extension code runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtensionContext {
+    /// Background page
+    Background,
+    /// Toolbar popup
+    Popup,
+    /// Content scripts in a tab's page
+    ContentScript(TabId),
+}
+
+/// A tab as extension APIs see it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabInfo {
+    pub id: TabId,
+    pub url: String,
+    pub title: String,
+    pub active: bool,
+}
+
+impl TabInfo {
+    /// Whether an extension may see the tab's URL and title: with the
+    /// "tabs" permission, a host permission matching the URL, or
+    /// "activeTab" for the active tab
+    fn visible_to(&self, permissions: &[Permission]) -> bool {
+        permissions.iter().any(|p| match p {
+            Permission::Tabs => true,
+            Permission::ActiveTab => self.active,
+            Permission::Host(pattern) => MatchPattern::parse(pattern).is_ok_and(|m| m.matches(&self.url)),
+            _ => false,
+        })
+    }
+    
+    /// The `tabs.Tab` object
+    fn to_js(&self, permissions: &[Permission]) -> HostValue {
+        let mut properties = vec![("id", self.id.into_js()), ("active", self.active.into_js())];
+        if self.visible_to(permissions) {
+            properties.push(("url", self.url.as_str().into_js()));
+            properties.push(("title", self.title.as_str().into_js()));
+        }
+        HostValue::object(properties)
+    }
+    
+    /// Whether the tab matches a `tabs.query` filter: `active`, and `url`
+    /// as a match pattern or list of them, which only matches tabs whose
+    /// URL the extension may see
+    fn matches_query(&self, query: &HostValue, permissions: &[Permission]) -> bool {
+        if query.get("active").and_then(HostValue::as_bool).is_some_and(|active| active != self.active) {
+            return false;
+        }
+        let patterns: Vec<&str> = match query.get("url") {
+            Some(HostValue::Array(items)) => items.iter().filter_map(HostValue::as_str).collect(),
+            Some(url) => url.as_str().into_iter().collect(),
+            None => return true,
+        };
+        self.visible_to(permissions)
+            && patterns.iter().any(|p| MatchPattern::parse(p).is_ok_and(|m| m.matches(&self.url)))
+    }
+}
+
+/// The browser's tabs, as extension APIs act on them
+pub trait ExtensionTabs {
+    /// Tabs in tab strip order
+    fn tabs(&self) -> Vec<TabInfo>;
+    /// Open a tab, activating it if `active`
+    fn create(&mut self, url: &str, active: bool) -> TabInfo;
+    /// Navigate a tab and activate it; None if there is no such tab
+    fn update(&mut self, id: TabId, url: Option<&str>, active: bool) -> Option<TabInfo>;
+}
+
+/// Storage area of `chrome.storage`
+///
+/// Both are kept on this device; `sync` has the smaller quotas of storage
+/// that follows the user between browsers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageArea {
+    Local,
+    Sync,
+}
+
+impl StorageArea {
+    /// Storage origin of an extension's area
+    fn origin(self, ext_id: &str) -> String {
+        match self {
+            Self::Local => format!("extension://{}", ext_id),
+            Self::Sync => format!("extension-sync://{}", ext_id),
+        }
+    }
+    
+    /// Bytes of keys and JSON values the area holds at most
+    pub fn quota_bytes(self) -> usize {
+        match self {
+            Self::Local => 10 * 1024 * 1024,
+            Self::Sync => 100 * 1024,
+        }
+    }
+    
+    /// Bytes of one key and its JSON value at most
+    pub fn quota_bytes_per_item(self) -> Option<usize> {
+        match self {
+            Self::Local => None,
+            Self::Sync => Some(8 * 1024),
+        }
+    }
+    
+    /// Items the area holds at most
+    pub fn max_items(self) -> Option<usize> {
+        match self {
+            Self::Local => None,
+            Self::Sync => Some(512),
+        }
+    }
+}
+
+/// Recipients of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    /// Background page and popup, from `runtime.sendMessage`
+    ExtensionPages,
+    /// Content scripts in a tab, from `tabs.sendMessage`
+    Tab(TabId),
+}
+
+/// `runtime.onMessage` listener
+#[derive(Debug, Clone)]
+struct Listener {
+    ext_id: String,
+    context: ExtensionContext,
+    callback: Callback,
+}
+
+/// Item added with `contextMenus.create`
+#[derive(Debug, Clone)]
+struct MenuItem {
+    ext_id: String,
+    id: String,
+    /// `%s` stands for the selected text
+    title: String,
+    contexts: Vec<MenuContext>,
+    /// Pages the item is shown on; any page when empty
+    document_url_patterns: Vec<MatchPattern>,
+    enabled: bool,
+}
+
+impl MenuItem {
+    /// Set the properties given to `create` or `update`
+    fn apply(&mut self, properties: &HostValue) -> Result<(), JsError> {
+        if let Some(title) = properties.get("title").and_then(HostValue::as_str) {
+            self.title = title.to_string();
+        }
+        if let Some(enabled) = properties.get("enabled").and_then(HostValue::as_bool) {
+            self.enabled = enabled;
+        }
+        if let Some(contexts) = properties.get("contexts") {
+            let names: Vec<&str> = match contexts {
+                HostValue::Array(items) => items.iter().filter_map(HostValue::as_str).collect(),
+                context => context.as_str().into_iter().collect(),
+            };
+            self.contexts = names.into_iter()
+                .map(|name| MenuContext::parse(name).ok_or_else(|| JsError::TypeError(format!("unknown menu context: {}", name))))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(HostValue::Array(patterns)) = properties.get("documentUrlPatterns") {
+            self.document_url_patterns = patterns.iter().filter_map(HostValue::as_str)
+                .map(|p| MatchPattern::parse(p).map_err(|e| JsError::TypeError(e.to_string())))
+                .collect::<Result<_, _>>()?;
+        }
+        Ok(())
+    }
+    
+    /// Whether the item is shown for a click
+    fn shown_for(&self, hit: &HitTestResult) -> bool {
+        self.contexts.iter().any(|c| c.applies_to(hit))
+            && (self.document_url_patterns.is_empty() || self.document_url_patterns.iter().any(|p| p.matches(&hit.page_url)))
+    }
+}
+
+/// Work queued by API calls
+#[derive(Debug)]
+enum Task {
+    Callback {
+        callback: Callback,
+        args: Vec<HostValue>,
+    },
+    QueryTabs {
+        permissions: Vec<Permission>,
+        query: HostValue,
+        callback: Callback,
+    },
+    CreateTab {
+        permissions: Vec<Permission>,
+        url: String,
+        active: bool,
+        callback: Option<Callback>,
+    },
+    UpdateTab {
+        permissions: Vec<Permission>,
+        /// None for the active tab
+        tab: Option<TabId>,
+        url: Option<String>,
+        active: bool,
+        callback: Option<Callback>,
+    },
+    Message {
+        ext_id: String,
+        permissions: Vec<Permission>,
+        from: ExtensionContext,
+        target: Target,
+        message: HostValue,
+        response: Option<Callback>,
+    },
+    MenuClicked {
+        ext_id: String,
+        permissions: Vec<Permission>,
+        info: HostValue,
+        tab: TabId,
+    },
+}
+
+/// State the API functions share
+#[derive(Default)]
+struct ApiState {
+    storage: StorageManager,
+    listeners: Vec<Listener>,
+    /// `contextMenus.onClicked` listeners
+    menu_listeners: Vec<Listener>,
+    menu_items: Vec<MenuItem>,
+    /// Numbers IDs of menu items created without one
+    next_menu_id: u64,
+    tasks: Vec<Task>,
+}
+
+impl ApiState {
+    /// `storage.get`: items for a key, a list of keys, an object of keys
+    /// with defaults, or null for everything
+    fn storage_get(&mut self, area: StorageArea, ext_id: &str, keys: &HostValue) -> HostValue {
+        let origin = area.origin(ext_id);
+        let wanted: Vec<(String, Option<HostValue>)> = match keys {
+            HostValue::Value(JsValue::Null | JsValue::Undefined) => {
+                self.storage.local_keys(&origin).into_iter().map(|k| (k, None)).collect()
+            }
+            HostValue::Object(defaults) => defaults.iter().map(|(k, v)| (k.clone(), Some(v.clone()))).collect(),
+            HostValue::Array(items) => items.iter().filter_map(HostValue::as_str).map(|k| (k.to_string(), None)).collect(),
+            key => key.as_str().map(|k| vec![(k.to_string(), None)]).unwrap_or_default(),
+        };
+        
+        let mut items = Vec::new();
+        for (key, default) in wanted {
+            let stored = self.storage.local_get(&origin, &key).and_then(|json| HostValue::from_json(&json));
+            if let Some(value) = stored.or(default) {
+                items.push((key, value));
+            }
+        }
+        HostValue::Object(items)
+    }
+    
+    /// `storage.set`: store each property of an object as JSON, all or
+    /// none of them within the area's quotas
+    fn storage_set(&mut self, area: StorageArea, ext_id: &str, items: &HostValue) -> Result<(), JsError> {
+        let HostValue::Object(items) = items else {
+            return Err(JsError::TypeError("storage items must be an object".to_string()));
+        };
+        let origin = area.origin(ext_id);
+        
+        let mut stored: HashMap<String, usize> = self.storage.local_keys(&origin).into_iter()
+            .map(|k| {
+                let bytes = k.len() + self.storage.local_get(&origin, &k).map_or(0, |v| v.len());
+                (k, bytes)
+            })
+            .collect();
+        let mut writes = Vec::new();
+        for (key, value) in items {
+            if value.is_undefined() || matches!(value, HostValue::Function(_)) {
+                continue;
+            }
+            let json = value.to_json();
+            let bytes = key.len() + json.len();
+            if area.quota_bytes_per_item().is_some_and(|quota| bytes > quota) {
+                return Err(JsError::Runtime("QUOTA_BYTES_PER_ITEM quota exceeded".to_string()));
+            }
+            stored.insert(key.clone(), bytes);
+            writes.push((key, json));
+        }
+        if area.max_items().is_some_and(|max| stored.len() > max) {
+            return Err(JsError::Runtime("MAX_ITEMS quota exceeded".to_string()));
+        }
+        if stored.values().sum::<usize>() > area.quota_bytes() {
+            return Err(JsError::Runtime("QUOTA_BYTES quota exceeded".to_string()));
+        }
+        
+        for (key, json) in writes {
+            self.storage.local_set(&origin, key, &json);
+        }
+        Ok(())
+    }
+    
+    /// `storage.remove`: a key or a list of keys
+    fn storage_remove(&mut self, area: StorageArea, ext_id: &str, keys: &HostValue) {
+        let origin = area.origin(ext_id);
+        let keys: Vec<&str> = match keys {
+            HostValue::Array(items) => items.iter().filter_map(HostValue::as_str).collect(),
+            key => key.as_str().into_iter().collect(),
+        };
+        for key in keys {
+            self.storage.local_remove(&origin, key);
+        }
+    }
+    
+    /// Queue a callback, if there is one
+    fn callback(&mut self, callback: Option<Callback>, args: Vec<HostValue>) {
+        if let Some(callback) = callback {
+            self.tasks.push(Task::Callback { callback, args });
+        }
+    }
+}
+
+/// Extension APIs and the extension pages they are used from
+pub struct ExtensionRuntime {
+    state: Arc<Mutex<ApiState>>,
+    /// Background pages by extension ID
+    backgrounds: HashMap<String, PageJsRuntime>,
+    /// Open popups by extension ID
+    popups: HashMap<String, PageJsRuntime>,
+}
+
+impl ExtensionRuntime {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ApiState::default())),
+            backgrounds: HashMap::new(),
+            popups: HashMap::new(),
+        }
+    }
+    
+    /// The `chrome` global for an extension's code in a context:
+    /// `runtime` everywhere, `storage` with the "storage" permission, and
+    /// `tabs` and, with the "contextMenus" permission, `contextMenus`
+    /// outside content scripts
+    pub fn api(&self, ext: &Extension, context: ExtensionContext) -> HostApi {
+        let mut chrome = HostObject::new().object("runtime", self.runtime_api(ext, context));
+        if ext.has_permission(&Permission::Storage) {
+            chrome = chrome.object("storage", HostObject::new()
+                .object("local", self.storage_api(&ext.id, StorageArea::Local))
+                .object("sync", self.storage_api(&ext.id, StorageArea::Sync)));
+        }
+        if !matches!(context, ExtensionContext::ContentScript(_)) {
+            chrome = chrome.object("tabs", self.tabs_api(ext, context));
+            if ext.has_permission(&Permission::ContextMenus) {
+                chrome = chrome.object("contextMenus", self.context_menus_api(&ext.id, context));
+            }
+        }
+        HostApi::new().object("chrome", chrome)
+    }
+    
+    fn runtime_api(&self, ext: &Extension, context: ExtensionContext) -> HostObject {
+        let id = ext.id.clone();
+        let permissions = ext.manifest.permissions.clone();
+        let state = self.state.clone();
+        let send_message = move |message: HostValue, response: Option<Callback>| {
+            state.lock().unwrap().tasks.push(Task::Message {
+                ext_id: id.clone(),
+                permissions: permissions.clone(),
+                from: context,
+                target: Target::ExtensionPages,
+                message,
+                response,
+            });
+            Ok(())
+        };
+        
+        let id = ext.id.clone();
+        let state = self.state.clone();
+        let add_listener = move |callback: Callback| {
+            state.lock().unwrap().listeners.push(Listener { ext_id: id.clone(), context, callback });
+            Ok(())
+        };
+        
+        let id = ext.id.clone();
+        HostObject::new()
+            .value("id", ext.id.as_str())
+            .function("getURL", move |path: String| Ok(resource_url(&id, &path)))
+            .function("sendMessage", send_message)
+            .object("onMessage", HostObject::new().function("addListener", add_listener))
+    }
+    
+    fn storage_api(&self, ext_id: &str, area: StorageArea) -> HostObject {
+        let (id, state) = (ext_id.to_string(), self.state.clone());
+        let get = move |keys: HostValue, callback: Option<Callback>| {
+            let mut state = state.lock().unwrap();
+            let items = state.storage_get(area, &id, &keys);
+            state.callback(callback, vec![items.clone()]);
+            Ok(items)
+        };
+        
+        let (id, state) = (ext_id.to_string(), self.state.clone());
+        let set = move |items: HostValue, callback: Option<Callback>| {
+            let mut state = state.lock().unwrap();
+            state.storage_set(area, &id, &items)?;
+            state.callback(callback, Vec::new());
+            Ok(())
+        };
+        
+        let (id, state) = (ext_id.to_string(), self.state.clone());
+        let remove = move |keys: HostValue, callback: Option<Callback>| {
+            let mut state = state.lock().unwrap();
+            state.storage_remove(area, &id, &keys);
+            state.callback(callback, Vec::new());
+            Ok(())
+        };
+        
+        let (id, state) = (ext_id.to_string(), self.state.clone());
+        let clear = move |callback: Option<Callback>| {
+            let mut state = state.lock().unwrap();
+            state.storage.local_clear(&area.origin(&id));
+            state.callback(callback, Vec::new());
+            Ok(())
+        };
+        
+        HostObject::new()
+            .value("QUOTA_BYTES", area.quota_bytes())
+            .function("get", get)
+            .function("set", set)
+            .function("remove", remove)
+            .function("clear", clear)
+    }
+    
+    fn tabs_api(&self, ext: &Extension, context: ExtensionContext) -> HostObject {
+        let (permissions, state) = (ext.manifest.permissions.clone(), self.state.clone());
+        let query = move |query: HostValue, callback: Callback| {
+            state.lock().unwrap().tasks.push(Task::QueryTabs { permissions: permissions.clone(), query, callback });
+            Ok(())
+        };
+        
+        let (permissions, state) = (ext.manifest.permissions.clone(), self.state.clone());
+        let create = move |properties: HostValue, callback: Option<Callback>| {
+            state.lock().unwrap().tasks.push(Task::CreateTab {
+                permissions: permissions.clone(),
+                url: properties.get("url").and_then(HostValue::as_str).unwrap_or("about:blank").to_string(),
+                active: properties.get("active").and_then(HostValue::as_bool).unwrap_or(true),
+                callback,
+            });
+            Ok(())
+        };
+        
+        // update([tabId], properties, [callback])
+        let (permissions, state) = (ext.manifest.permissions.clone(), self.state.clone());
+        let update = move |first: HostValue, second: HostValue, third: Option<Callback>| {
+            let (tab, properties, callback) = match first.as_number() {
+                Some(id) => (Some(id as TabId), second, third),
+                None => (None, first, fos_js::FromJs::from_js(&second)?),
+            };
+            state.lock().unwrap().tasks.push(Task::UpdateTab {
+                permissions: permissions.clone(),
+                tab,
+                url: properties.get("url").and_then(HostValue::as_str).map(str::to_string),
+                active: properties.get("active").and_then(HostValue::as_bool).unwrap_or(false),
+                callback,
+            });
+            Ok(())
+        };
+        
+        let (id, permissions, state) = (ext.id.clone(), ext.manifest.permissions.clone(), self.state.clone());
+        let send_message = move |tab: TabId, message: HostValue, response: Option<Callback>| {
+            state.lock().unwrap().tasks.push(Task::Message {
+                ext_id: id.clone(),
+                permissions: permissions.clone(),
+                from: context,
+                target: Target::Tab(tab),
+                message,
+                response,
+            });
+            Ok(())
+        };
+        
+        HostObject::new()
+            .function("query", query)
+            .function("create", create)
+            .function("update", update)
+            .function("sendMessage", send_message)
+    }
+    
+    fn context_menus_api(&self, ext_id: &str, context: ExtensionContext) -> HostObject {
+        let (id, state) = (ext_id.to_string(), self.state.clone());
+        let create = move |properties: HostValue, callback: Option<Callback>| {
+            let mut state = state.lock().unwrap();
+            let item_id = match properties.get("id").and_then(HostValue::as_str) {
+                Some(item_id) => item_id.to_string(),
+                None => {
+                    state.next_menu_id += 1;
+                    state.next_menu_id.to_string()
+                }
+            };
+            if state.menu_items.iter().any(|item| item.ext_id == id && item.id == item_id) {
+                return Err(JsError::Runtime(format!("Cannot create item with duplicate id {}", item_id)));
+            }
+            let mut item = MenuItem {
+                ext_id: id.clone(),
+                id: item_id.clone(),
+                title: String::new(),
+                contexts: vec![MenuContext::Page],
+                document_url_patterns: Vec::new(),
+                enabled: true,
+            };
+            item.apply(&properties)?;
+            state.menu_items.push(item);
+            state.callback(callback, Vec::new());
+            Ok(item_id)
+        };
+        
+        let (id, state) = (ext_id.to_string(), self.state.clone());
+        let update = move |item_id: String, properties: HostValue, callback: Option<Callback>| {
+            let mut state = state.lock().unwrap();
+            let item = state.menu_items.iter_mut().find(|item| item.ext_id == id && item.id == item_id)
+                .ok_or_else(|| JsError::Runtime(format!("Cannot find menu item with id {}", item_id)))?;
+            item.apply(&properties)?;
+            state.callback(callback, Vec::new());
+            Ok(())
+        };
+        
+        let (id, state) = (ext_id.to_string(), self.state.clone());
+        let remove = move |item_id: String, callback: Option<Callback>| {
+            let mut state = state.lock().unwrap();
+            state.menu_items.retain(|item| item.ext_id != id || item.id != item_id);
+            state.callback(callback, Vec::new());
+            Ok(())
+        };
+        
+        let (id, state) = (ext_id.to_string(), self.state.clone());
+        let remove_all = move |callback: Option<Callback>| {
+            let mut state = state.lock().unwrap();
+            state.menu_items.retain(|item| item.ext_id != id);
+            state.callback(callback, Vec::new());
+            Ok(())
+        };
+        
+        let (id, state) = (ext_id.to_string(), self.state.clone());
+        let add_listener = move |callback: Callback| {
+            state.lock().unwrap().menu_listeners.push(Listener { ext_id: id.clone(), context, callback });
+            Ok(())
+        };
+        
+        HostObject::new()
+            .function("create", create)
+            .function("update", update)
+            .function("remove", remove)
+            .function("removeAll", remove_all)
+            .object("onClicked", HostObject::new().function("addListener", add_listener))
+    }
+    
+    // === Context Menu ===
+    
+    /// Items extensions added for a click, to show in the context menu
+    pub fn context_menu_items(&self, hit: &HitTestResult) -> Vec<ContextMenuItem> {
+        let selection = hit.selection_text.as_deref().unwrap_or("");
+        self.state.lock().unwrap().menu_items.iter()
+            .filter(|item| item.shown_for(hit))
+            .map(|item| {
+                let action = ContextMenuAction::Extension { ext_id: item.ext_id.clone(), item_id: item.id.clone() };
+                ContextMenuItem::new(&item.title.replace("%s", selection), action).with_enabled(item.enabled)
+            })
+            .collect()
+    }
+    
+    /// Queue `contextMenus.onClicked` for an extension's item chosen in the
+    /// context menu of a tab
+    pub fn menu_item_clicked(&self, ext: &Extension, item_id: &str, hit: &HitTestResult, tab: TabId) {
+        let mut info = vec![
+            ("menuItemId", item_id.into_js()),
+            ("pageUrl", hit.page_url.as_str().into_js()),
+            ("editable", hit.editable.into_js()),
+        ];
+        if let Some(url) = &hit.link_url {
+            info.push(("linkUrl", url.as_str().into_js()));
+        }
+        let src = hit.image_url.as_ref().map(|url| ("image", url))
+            .or_else(|| hit.media.as_ref().map(|(kind, url)| (if *kind == MediaKind::Video { "video" } else { "audio" }, url)));
+        if let Some((media_type, url)) = src {
+            info.push(("mediaType", media_type.into_js()));
+            info.push(("srcUrl", url.as_str().into_js()));
+        }
+        if let Some(text) = &hit.selection_text {
+            info.push(("selectionText", text.as_str().into_js()));
+        }
+        self.state.lock().unwrap().tasks.push(Task::MenuClicked {
+            ext_id: ext.id.clone(),
+            permissions: ext.manifest.permissions.clone(),
+            info: HostValue::object(info),
+            tab,
+        });
+    }
+    
+    // === Extension Pages ===
+    
+    /// Start background pages of enabled extensions and stop those of
+    /// extensions disabled or unloaded since
+    pub fn sync(&mut self, extensions: &ExtensionManager) {
+        let enabled: Vec<_> = extensions.enabled();
+        let running: Vec<String> = self.backgrounds.keys().cloned().collect();
+        for id in running {
+            if !enabled.iter().any(|ext| ext.lock().unwrap().id == id) {
+                self.stop_background(&id);
+                self.close_popup(&id);
+                self.state.lock().unwrap().menu_items.retain(|item| item.ext_id != id);
+            }
+        }
+        for ext in enabled {
+            let ext = ext.lock().unwrap();
+            if ext.manifest.background.is_some() && !self.backgrounds.contains_key(&ext.id) {
+                self.start_background(&ext);
+            }
+        }
+    }
+    
+    /// Run an extension's background scripts in a page of their own;
+    /// false if it has none or its context could not be created
+    pub fn start_background(&mut self, ext: &Extension) -> bool {
+        let Some(ref background) = ext.manifest.background else {
+            return false;
+        };
+        self.stop_background(&ext.id);
+        
+        let url = resource_url(&ext.id, "_generated_background_page.html");
+        let mut runtime = PageJsRuntime::new(&url);
+        if let Err(e) = runtime.initialize(Arc::new(Mutex::new(Document::new(&url)))) {
+            log::warn!("Failed to start background page of {}: {}", ext.id, e);
+            return false;
+        }
+        runtime.install_host_api(&self.api(ext, ExtensionContext::Background));
+        
+        for path in &background.scripts {
+            match ext.resources.get(path) {
+                Some(source) => {
+                    let _ = runtime.execute_external_script(&resource_url(&ext.id, path), source);
+                }
+                None => log::warn!("Background script {} missing from extension {}", path, ext.id),
+            }
+        }
+        
+        log::info!("Started background page of {}", ext.id);
+        self.backgrounds.insert(ext.id.clone(), runtime);
+        true
+    }
+    
+    pub fn stop_background(&mut self, ext_id: &str) {
+        if self.backgrounds.remove(ext_id).is_some() {
+            self.forget(ext_id, ExtensionContext::Background);
+        }
+    }
+    
+    /// Open the popup of an extension's browser or page action; false if
+    /// it has none. Scripts load from the extension's files; inline ones do
+    /// not run, as under the default extension content security policy.
+    pub fn open_popup(&mut self, ext: &Extension) -> bool {
+        let popup = ext.manifest.browser_action.as_ref().and_then(|a| a.default_popup.as_ref())
+            .or_else(|| ext.manifest.page_action.as_ref().and_then(|a| a.default_popup.as_ref()));
+        let Some(path) = popup else {
+            return false;
+        };
+        let Some(html) = ext.resources.get(path) else {
+            log::warn!("Popup {} missing from extension {}", path, ext.id);
+            return false;
+        };
+        self.close_popup(&ext.id);
+        
+        let url = resource_url(&ext.id, path);
+        let document = Arc::new(Mutex::new(fos_html::parse_with_url(html, &url)));
+        let mut runtime = PageJsRuntime::new(&url);
+        if let Err(e) = runtime.initialize(document.clone()) {
+            log::warn!("Failed to open popup of {}: {}", ext.id, e);
+            return false;
+        }
+        runtime.install_host_api(&self.api(ext, ExtensionContext::Popup));
+        runtime.extract_scripts(&document.lock().unwrap());
+        
+        for src in runtime.pending_external_scripts() {
+            let script = resolve_resource(&ext.id, path, &src);
+            match ext.resources.get(&script) {
+                Some(source) => {
+                    let _ = runtime.execute_external_script(&src, source);
+                }
+                None => log::warn!("Popup script {} missing from extension {}", script, ext.id),
+            }
+        }
+        
+        self.popups.insert(ext.id.clone(), runtime);
+        true
+    }
+    
+    pub fn close_popup(&mut self, ext_id: &str) {
+        if self.popups.remove(ext_id).is_some() {
+            self.forget(ext_id, ExtensionContext::Popup);
+        }
+    }
+    
+    pub fn background(&self, ext_id: &str) -> Option<&PageJsRuntime> {
+        self.backgrounds.get(ext_id)
+    }
+    
+    pub fn popup(&self, ext_id: &str) -> Option<&PageJsRuntime> {
+        self.popups.get(ext_id)
+    }
+    
+    /// Drop the listeners of an extension context that went away
+    fn forget(&self, ext_id: &str, context: ExtensionContext) {
+        let mut state = self.state.lock().unwrap();
+        state.listeners.retain(|l| l.ext_id != ext_id || l.context != context);
+        state.menu_listeners.retain(|l| l.ext_id != ext_id || l.context != context);
+    }
+    
+    /// Process timers of background pages and popups
+    pub fn process_timers(&self) {
+        for (id, runtime) in self.backgrounds.iter().chain(&self.popups) {
+            if let Err(e) = runtime.process_timers() {
+                log::warn!("Timer error in extension {}: {}", id, e);
+            }
+        }
+    }
+    
+    // === Dispatch ===
+    
+    /// Carry out queued API work: tab queries and changes, callbacks and
+    /// message deliveries. `page` is the runtime of the page loaded in a
+    /// tab, whose content scripts can receive messages; returns how many
+    /// tasks ran.
+    pub fn dispatch(&mut self, tabs: &mut dyn ExtensionTabs, page: Option<(TabId, &PageJsRuntime)>) -> usize {
+        let mut handled = 0;
+        for _ in 0..MAX_DISPATCH_ROUNDS {
+            let tasks = std::mem::take(&mut self.state.lock().unwrap().tasks);
+            if tasks.is_empty() {
+                break;
+            }
+            handled += tasks.len();
+            for task in tasks {
+                self.run_task(task, tabs, page);
+            }
+        }
+        handled
+    }
+    
+    fn run_task(&self, task: Task, tabs: &mut dyn ExtensionTabs, page: Option<(TabId, &PageJsRuntime)>) {
+        match task {
+            Task::Callback { callback, args } => {
+                self.call(page, &callback, &args);
+            }
+            Task::QueryTabs { permissions, query, callback } => {
+                let found: Vec<HostValue> = tabs.tabs().iter()
+                    .filter(|tab| tab.matches_query(&query, &permissions))
+                    .map(|tab| tab.to_js(&permissions))
+                    .collect();
+                self.call(page, &callback, &[HostValue::Array(found)]);
+            }
+            Task::CreateTab { permissions, url, active, callback } => {
+                let tab = tabs.create(&url, active);
+                if let Some(callback) = callback {
+                    self.call(page, &callback, &[tab.to_js(&permissions)]);
+                }
+            }
+            Task::UpdateTab { permissions, tab, url, active, callback } => {
+                let id = tab.or_else(|| tabs.tabs().iter().find(|t| t.active).map(|t| t.id));
+                let tab = id.and_then(|id| tabs.update(id, url.as_deref(), active));
+                if let Some(callback) = callback {
+                    self.call(page, &callback, &[tab.map_or(HostValue::UNDEFINED, |t| t.to_js(&permissions))]);
+                }
+            }
+            Task::Message { ext_id, permissions, from, target, message, response } => {
+                let mut sender = vec![("id", ext_id.as_str().into_js())];
+                if let ExtensionContext::ContentScript(tab) = from {
+                    if let Some(tab) = tabs.tabs().iter().find(|t| t.id == tab) {
+                        sender.push(("tab", tab.to_js(&permissions)));
+                    }
+                }
+                let sender = HostValue::object(sender);
+                let reply = self.deliver(&ext_id, from, target, &[message, sender], page);
+                if let Some(response) = response {
+                    self.call(page, &response, &[reply.unwrap_or(HostValue::UNDEFINED)]);
+                }
+            }
+            Task::MenuClicked { ext_id, permissions, info, tab } => {
+                let tab = tabs.tabs().into_iter().find(|t| t.id == tab)
+                    .map_or(HostValue::UNDEFINED, |t| t.to_js(&permissions));
+                let listeners: Vec<Listener> = self.state.lock().unwrap().menu_listeners.iter()
+                    .filter(|l| l.ext_id == ext_id)
+                    .cloned()
+                    .collect();
+                for listener in listeners {
+                    if let Some(Err(e)) = self.call(page, &listener.callback, &[info.clone(), tab.clone()]) {
+                        log::warn!("Menu click listener of {} failed: {}", ext_id, e);
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Call the listeners a message is for; the first value one returns
+    /// is the reply
+    fn deliver(&self, ext_id: &str, from: ExtensionContext, target: Target, args: &[HostValue], page: Option<(TabId, &PageJsRuntime)>) -> Option<HostValue> {
+        let listeners: Vec<Listener> = self.state.lock().unwrap().listeners.iter()
+            .filter(|l| l.ext_id == ext_id)
+            .filter(|l| match target {
+                Target::ExtensionPages => !matches!(l.context, ExtensionContext::ContentScript(_)) && l.context != from,
+                Target::Tab(tab) => l.context == ExtensionContext::ContentScript(tab),
+            })
+            .cloned()
+            .collect();
+        
+        let mut reply = None;
+        for listener in listeners {
+            // Content scripts of a tab whose page is not at hand wait
+            if let ExtensionContext::ContentScript(tab) = listener.context {
+                if page.is_none_or(|(t, _)| t != tab) {
+                    continue;
+                }
+            }
+            match self.call(page, &listener.callback, args) {
+                Some(Ok(value)) if reply.is_none() && !value.is_undefined() => reply = Some(value),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => log::warn!("Message listener of {} failed: {}", ext_id, e),
+                // Its page was closed or navigated away
+                None => self.state.lock().unwrap().listeners.retain(|l| l.callback != listener.callback),
+            }
+        }
+        reply
+    }
+    
+    /// Call a callback in whichever extension page or content script world
+    /// it belongs to; None if that realm is gone
+    fn call(&self, page: Option<(TabId, &PageJsRuntime)>, callback: &Callback, args: &[HostValue]) -> Option<Result<HostValue, JsError>> {
+        let result = self.backgrounds.values().chain(self.popups.values())
+            .chain(page.map(|(_, runtime)| runtime))
+            .find_map(|runtime| runtime.call(callback, args));
+        if let Some(Err(ref e)) = result {
+            log::warn!("Extension callback failed: {}", e);
+        }
+        result
+    }
+}
+
+impl Default for ExtensionRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// URL of a file packaged with an extension
+fn resource_url(ext_id: &str, path: &str) -> String {
+    format!("extension://{}/{}", ext_id, path.trim_start_matches('/'))
+}
+
+/// Path of a file a page of the extension refers to by `src`
+fn resolve_resource(ext_id: &str, page_path: &str, src: &str) -> String {
+    let root = resource_url(ext_id, "");
+    if let Some(path) = src.strip_prefix(&root).or_else(|| src.strip_prefix('/')) {
+        return path.to_string();
+    }
+    let src = src.trim_start_matches("./");
+    match page_path.trim_start_matches('/').rsplit_once('/') {
+        Some((dir, _)) => format!("{}/{}", dir, src),
+        None => src.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::{BackgroundScript, BrowserAction, ExtensionManifest};
+    
+    #[derive(Default)]
+    struct Tabs(Vec<TabInfo>);
+    
+    impl ExtensionTabs for Tabs {
+        fn tabs(&self) -> Vec<TabInfo> {
+            self.0.clone()
+        }
+        
+        fn create(&mut self, url: &str, active: bool) -> TabInfo {
+            if active {
+                self.0.iter_mut().for_each(|t| t.active = false);
+            }
+            let tab = TabInfo { id: self.0.len() as TabId + 1, url: url.to_string(), title: String::new(), active };
+            self.0.push(tab.clone());
+            tab
+        }
+        
+        fn update(&mut self, id: TabId, url: Option<&str>, active: bool) -> Option<TabInfo> {
+            let tab = self.0.iter_mut().find(|t| t.id == id)?;
+            if let Some(url) = url {
+                tab.url = url.to_string();
+            }
+            tab.active |= active;
+            Some(tab.clone())
+        }
+    }
+    
+    fn extension(permissions: Vec<Permission>) -> Extension {
+        let mut ext = Extension::new("ext", ExtensionManifest {
+            name: "Test".to_string(),
+            version: "1.0".to_string(),
+            description: String::new(),
+            permissions,
+            content_scripts: vec![],
+            background: Some(BackgroundScript { scripts: vec!["bg.js".to_string()], persistent: false }),
+            browser_action: Some(BrowserAction {
+                default_icon: None,
+                default_title: None,
+                default_popup: Some("popup/popup.html".to_string()),
+            }),
+            page_action: None,
+            declarative_net_request: vec![],
+        });
+        ext.resources.insert("bg.js".to_string(), "function onMessage(message, sender) { got = message; return 'pong'; } chrome.runtime.onMessage.addListener(onMessage);".to_string());
+        ext.resources.insert("popup/popup.html".to_string(), "<html><body><script src=\"popup.js\"></script></body></html>".to_string());
+        ext.resources.insert("popup/popup.js".to_string(), "function reply(r) { answer = r; } chrome.runtime.sendMessage('ping', reply);".to_string());
+        ext
+    }
+    
+    fn blank_page(url: &str) -> PageJsRuntime {
+        let mut page = PageJsRuntime::new(url);
+        page.initialize(Arc::new(Mutex::new(Document::new(url)))).unwrap();
+        page
+    }
+    
+    #[test]
+    fn test_storage_areas() {
+        let runtime = ExtensionRuntime::new();
+        let ext = extension(vec![Permission::Storage]);
+        let page = blank_page("https://example.com/");
+        page.install_host_api(&runtime.api(&ext, ExtensionContext::ContentScript(1)));
+        
+        page.eval("chrome.storage.local.set({ color: 'red', size: 3 });").unwrap();
+        assert_eq!(page.eval("items = chrome.storage.local.get('color'); items.color;").unwrap().as_string(), Some("red"));
+        assert_eq!(page.eval("items = chrome.storage.sync.get({ color: 'blue' }); items.color;").unwrap().as_string(), Some("blue"));
+        assert_eq!(page.eval("typeof chrome.tabs;").unwrap().as_string(), Some("undefined"));
+        
+        // Over sync's per-item quota, nothing is written
+        let big = "x".repeat(9000);
+        assert!(page.eval(&format!("chrome.storage.sync.set({{ small: 1, big: '{}' }});", big)).is_err());
+        let mut state = runtime.state.lock().unwrap();
+        assert_eq!(state.storage.local_get("extension://ext", "color").as_deref(), Some("\"red\""));
+        assert!(state.storage.local_get("extension-sync://ext", "small").is_none());
+        drop(state);
+        
+        let denied = extension(vec![]);
+        let background = blank_page("extension://denied/");
+        background.install_host_api(&runtime.api(&denied, ExtensionContext::Background));
+        assert_eq!(background.eval("typeof chrome.storage;").unwrap().as_string(), Some("undefined"));
+        assert_eq!(background.eval("typeof chrome.tabs.query;").unwrap().as_string(), Some("function"));
+    }
+    
+    #[test]
+    fn test_messaging_and_tabs() {
+        let mut runtime = ExtensionRuntime::new();
+        let ext = extension(vec![Permission::Host("https://example.com/*".to_string())]);
+        let mut tabs = Tabs(vec![
+            TabInfo { id: 1, url: "https://example.com/".to_string(), title: "Example".to_string(), active: true },
+            TabInfo { id: 2, url: "https://other.org/".to_string(), title: "Other".to_string(), active: false },
+        ]);
+        assert!(runtime.start_background(&ext));
+        assert!(runtime.open_popup(&ext));
+        
+        // Popup to background, and the reply back
+        runtime.dispatch(&mut tabs, None);
+        assert_eq!(runtime.background("ext").unwrap().eval("got;").unwrap().as_string(), Some("ping"));
+        assert_eq!(runtime.popup("ext").unwrap().eval("answer;").unwrap().as_string(), Some("pong"));
+        
+        // Background to the content scripts in tab 1, and a new tab
+        let mut page = blank_page("https://example.com/");
+        page.set_world_api("ext", runtime.api(&ext, ExtensionContext::ContentScript(1)));
+        page.execute_in_world("ext", "function seen(m, sender) { return m; } chrome.runtime.onMessage.addListener(seen);", "extension://ext/content.js").unwrap();
+        let background = runtime.background("ext").unwrap();
+        background.eval("function echoed(r) { echo = r; } chrome.tabs.sendMessage(1, 'hello', echoed);").unwrap();
+        background.eval("chrome.tabs.create({ url: 'https://example.com/new', active: false });").unwrap();
+        runtime.dispatch(&mut tabs, Some((1, &page)));
+        assert_eq!(runtime.background("ext").unwrap().eval("echo;").unwrap().as_string(), Some("hello"));
+        assert_eq!(tabs.0.len(), 3);
+        assert!(!tabs.0[2].active);
+        
+        // URL and title only where the host permission reaches
+        let found = Arc::new(Mutex::new(None));
+        let slot = found.clone();
+        let background = runtime.background("ext").unwrap();
+        background.install_host_api(&HostApi::new().function("found", move |tabs: HostValue| {
+            *slot.lock().unwrap() = Some(tabs);
+            Ok(())
+        }));
+        background.eval("chrome.tabs.query({}, found);").unwrap();
+        runtime.dispatch(&mut tabs, None);
+        let found = found.lock().unwrap().take().unwrap();
+        assert_eq!(found.to_json(), concat!(
+            r#"[{"id":1,"active":true,"url":"https://example.com/","title":"Example"},"#,
+            r#"{"id":2,"active":false},"#,
+            r#"{"id":3,"active":false,"url":"https://example.com/new","title":""}]"#,
+        ));
+    }
+    
+    #[test]
+    fn test_context_menu_items() {
+        let mut runtime = ExtensionRuntime::new();
+        let ext = extension(vec![Permission::ContextMenus]);
+        let mut tabs = Tabs(vec![TabInfo { id: 1, url: "https://example.com/".to_string(), title: String::new(), active: true }]);
+        assert!(runtime.start_background(&ext));
+        let background = runtime.background("ext").unwrap();
+        background.eval(concat!(
+            "chrome.contextMenus.create({ id: 'define', title: 'Define \"%s\"', contexts: ['selection'] });",
+            "chrome.contextMenus.create({ id: 'docs', title: 'Docs', documentUrlPatterns: ['https://docs.example/*'] });",
+            "function clicked(info, tab) { clickedItem = info.menuItemId; selected = info.selectionText; clickedTab = tab.id; }",
+            "chrome.contextMenus.onClicked.addListener(clicked);",
+        )).unwrap();
+        assert!(background.eval("chrome.contextMenus.create({ id: 'docs' });").is_err());
+        
+        assert!(runtime.context_menu_items(&HitTestResult::new("https://example.com/")).is_empty());
+        let hit = HitTestResult::new("https://example.com/").with_selection("crate");
+        let items = runtime.context_menu_items(&hit);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].label, "Define \"crate\"");
+        assert_eq!(runtime.context_menu_items(&HitTestResult::new("https://docs.example/a")).len(), 1);
+        
+        runtime.menu_item_clicked(&ext, "define", &hit, 1);
+        runtime.dispatch(&mut tabs, None);
+        let background = runtime.background("ext").unwrap();
+        assert_eq!(background.eval("clickedItem;").unwrap().as_string(), Some("define"));
+        assert_eq!(background.eval("selected;").unwrap().as_string(), Some("crate"));
+        assert_eq!(background.eval("clickedTab;").unwrap().as_number(), Some(1.0));
+        
+        // Without the permission there is no API
+        let denied = extension(vec![]);
+        let background = blank_page("extension://denied/");
+        background.install_host_api(&runtime.api(&denied, ExtensionContext::Background));
+        assert_eq!(background.eval("typeof chrome.contextMenus;").unwrap().as_string(), Some("undefined"));
+    }
+}
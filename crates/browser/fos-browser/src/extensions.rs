@@ -1,9 +1,12 @@
 //! Browser extensions API
 //!
-//! Minimal extension support for browser customization.
+//! Minimal extension support for browser customization. Content scripts
+//! are matched to pages by match pattern and injected at document start,
+//! end or idle, each extension in its own isolated JavaScript world.
 
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use fos_dom::url::Url;
 
 /// Extension manifest
 #[derive(Debug, Clone)]
@@ -16,6 +19,8 @@ pub struct ExtensionManifest {
     pub background: Option<BackgroundScript>,
     pub browser_action: Option<BrowserAction>,
     pub page_action: Option<PageAction>,
+    /// Static request rulesets, `declarative_net_request.rule_resources`
+    pub declarative_net_request: Vec<RulesetDeclaration>,
 }
 
 /// Extension permission
@@ -32,6 +37,11 @@ pub enum Permission {
     History,
     Notifications,
     ContextMenus,
+    /// Block and allow requests; redirect and modify headers where a host
+    /// permission allows
+    DeclarativeNetRequest,
+    /// Request rules only where a host permission allows
+    DeclarativeNetRequestWithHostAccess,
     Host(String), // e.g., "*://*.example.com/*"
 }
 
@@ -49,6 +59,8 @@ impl Permission {
             "history" => Self::History,
             "notifications" => Self::Notifications,
             "contextMenus" => Self::ContextMenus,
+            "declarativeNetRequest" => Self::DeclarativeNetRequest,
+            "declarativeNetRequestWithHostAccess" => Self::DeclarativeNetRequestWithHostAccess,
             host => Self::Host(host.to_string()),
         }
     }
@@ -58,11 +70,134 @@ impl Permission {
 #[derive(Debug, Clone)]
 pub struct ContentScript {
     pub matches: Vec<String>,
+    /// Pages left out even when `matches` covers them
+    pub exclude_matches: Vec<String>,
     pub js: Vec<String>,
     pub css: Vec<String>,
     pub run_at: RunAt,
 }
 
+impl ContentScript {
+    /// Check if the script runs on URL: a `matches` pattern and no
+    /// `exclude_matches` pattern matches it
+    pub fn applies_to(&self, url: &str) -> bool {
+        let any = |patterns: &[String]| patterns.iter().any(|p| Extension::url_matches(url, p));
+        any(&self.matches) && !any(&self.exclude_matches)
+    }
+}
+
+/// Match pattern, `<scheme>://<host>/<path>` or `<all_urls>`
+///
+/// The scheme is `*` for http and https, or one scheme. The host is `*`
+/// for any host, `*.` and a domain for the domain and its subdomains, or
+/// one host. The path is a glob in which `*` matches anything, and is
+/// matched against the URL's path and query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchPattern {
+    schemes: Vec<String>,
+    /// None for any host
+    host: Option<String>,
+    subdomains: bool,
+    path: String,
+}
+
+impl MatchPattern {
+    const SCHEMES: [&'static str; 6] = ["http", "https", "ws", "wss", "ftp", "file"];
+    
+    pub fn parse(pattern: &str) -> Result<Self, ExtensionError> {
+        let invalid = || ExtensionError::InvalidManifest(format!("invalid match pattern: {}", pattern));
+        
+        if pattern == "<all_urls>" {
+            return Ok(Self {
+                schemes: Self::SCHEMES.iter().map(|s| s.to_string()).collect(),
+                host: None,
+                subdomains: false,
+                path: "/*".to_string(),
+            });
+        }
+        
+        let (scheme, rest) = pattern.split_once("://").ok_or_else(invalid)?;
+        let schemes = match scheme {
+            "*" => vec!["http".to_string(), "https".to_string()],
+            s if Self::SCHEMES.contains(&s) => vec![s.to_string()],
+            _ => return Err(invalid()),
+        };
+        
+        let slash = rest.find('/').ok_or_else(invalid)?;
+        let (host, path) = rest.split_at(slash);
+        let (host, subdomains) = match host {
+            "*" => (None, false),
+            h => match h.strip_prefix("*.") {
+                Some(domain) => (Some(domain), true),
+                None => (Some(h), false),
+            },
+        };
+        if host.is_some_and(|h| h.is_empty() || h.contains('*')) || (host.is_none() && scheme == "file") {
+            return Err(invalid());
+        }
+        
+        Ok(Self {
+            schemes,
+            host: host.map(str::to_ascii_lowercase),
+            subdomains,
+            path: path.to_string(),
+        })
+    }
+    
+    /// Check if the pattern matches URL; URLs that fail to parse never match
+    pub fn matches(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return false;
+        };
+        if !self.schemes.iter().any(|s| s == url.scheme()) {
+            return false;
+        }
+        
+        if let Some(ref host) = self.host {
+            let url_host = url.host_str().unwrap_or("").to_ascii_lowercase();
+            let subdomain = self.subdomains && url_host.strip_suffix(host.as_str()).is_some_and(|p| p.ends_with('.'));
+            if url_host != *host && !subdomain {
+                return false;
+            }
+        }
+        
+        let path = match url.query_params() {
+            Some(query) => format!("{}?{}", url.path(), query.to_string()),
+            None => url.path().to_string(),
+        };
+        glob_matches(&self.path, &path)
+    }
+}
+
+/// Match text against a glob in which `*` matches any run of characters
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = text.strip_prefix(parts.next().unwrap_or("")) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Content script file ready to inject
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentScriptSource {
+    /// Extension the script belongs to, which names its isolated world
+    pub extension_id: String,
+    /// `extension://<id>/<path>`, for error messages and coverage
+    pub url: String,
+    pub source: String,
+}
+
 /// When to run content scripts
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum RunAt {
@@ -72,6 +207,16 @@ pub enum RunAt {
     DocumentIdle,
 }
 
+/// A static ruleset in the manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RulesetDeclaration {
+    pub id: String,
+    /// Whether the ruleset is on when the extension loads
+    pub enabled: bool,
+    /// Packaged JSON file holding the rules
+    pub path: String,
+}
+
 /// Background script
 #[derive(Debug, Clone)]
 pub struct BackgroundScript {
@@ -102,6 +247,8 @@ pub struct Extension {
     pub manifest: ExtensionManifest,
     pub enabled: bool,
     pub storage: HashMap<String, String>,
+    /// Packaged files by path, e.g. content script sources
+    pub resources: HashMap<String, String>,
 }
 
 impl Extension {
@@ -111,6 +258,7 @@ impl Extension {
             manifest,
             enabled: true,
             storage: HashMap::new(),
+            resources: HashMap::new(),
         }
     }
     
@@ -122,22 +270,12 @@ impl Extension {
     /// Check if content script should run on URL
     pub fn matches_url(&self, url: &str) -> Vec<&ContentScript> {
         self.manifest.content_scripts.iter()
-            .filter(|cs| cs.matches.iter().any(|pattern| Self::url_matches(url, pattern)))
+            .filter(|cs| cs.applies_to(url))
             .collect()
     }
     
     fn url_matches(url: &str, pattern: &str) -> bool {
-        // Simple glob matching
-        if pattern == "<all_urls>" {
-            return true;
-        }
-        
-        // Convert pattern to regex-like matching
-        let pattern = pattern
-            .replace(".", r"\.")
-            .replace("*", ".*");
-        
-        url.contains(&pattern.replace(".*", ""))
+        MatchPattern::parse(pattern).is_ok_and(|p| p.matches(url))
     }
 }
 
@@ -158,6 +296,12 @@ impl ExtensionManager {
             return Err(ExtensionError::AlreadyLoaded);
         }
         
+        for cs in &manifest.content_scripts {
+            for pattern in cs.matches.iter().chain(&cs.exclude_matches) {
+                MatchPattern::parse(pattern)?;
+            }
+        }
+        
         let extension = Extension::new(id, manifest);
         self.extensions.insert(id.to_string(), Arc::new(Mutex::new(extension)));
         Ok(())
@@ -207,7 +351,7 @@ impl ExtensionManager {
             }
             
             for cs in &ext.manifest.content_scripts {
-                if cs.matches.iter().any(|p| Extension::url_matches(url, p)) {
+                if cs.applies_to(url) {
                     scripts.push((ext.id.clone(), cs.clone()));
                 }
             }
@@ -216,6 +360,46 @@ impl ExtensionManager {
         scripts
     }
     
+    /// Add a packaged file to an extension
+    pub fn add_resource(&self, ext_id: &str, path: &str, contents: &str) -> bool {
+        if let Some(ext) = self.extensions.get(ext_id) {
+            ext.lock().unwrap().resources.insert(path.to_string(), contents.to_string());
+            true
+        } else {
+            false
+        }
+    }
+    
+    /// Content script files to inject into the page at URL at one point of
+    /// its load, ordered by extension ID, then as listed in each manifest
+    pub fn scripts_to_inject(&self, url: &str, run_at: RunAt) -> Vec<ContentScriptSource> {
+        let mut ids: Vec<&String> = self.extensions.keys().collect();
+        ids.sort();
+        
+        let mut sources = Vec::new();
+        for id in ids {
+            let ext = self.extensions[id].lock().unwrap();
+            if !ext.enabled {
+                continue;
+            }
+            
+            for cs in ext.matches_url(url).into_iter().filter(|cs| cs.run_at == run_at) {
+                for path in &cs.js {
+                    let Some(source) = ext.resources.get(path) else {
+                        log::warn!("Content script {} missing from extension {}", path, ext.id);
+                        continue;
+                    };
+                    sources.push(ContentScriptSource {
+                        extension_id: ext.id.clone(),
+                        url: format!("extension://{}/{}", ext.id, path.trim_start_matches('/')),
+                        source: source.clone(),
+                    });
+                }
+            }
+        }
+        sources
+    }
+    
     /// Store data for extension
     pub fn storage_set(&self, ext_id: &str, key: &str, value: &str) -> bool {
         if let Some(ext) = self.extensions.get(ext_id) {
@@ -272,6 +456,7 @@ mod tests {
             background: None,
             browser_action: None,
             page_action: None,
+            declarative_net_request: vec![],
         };
         
         mgr.load("test-ext", manifest).unwrap();
@@ -284,4 +469,67 @@ mod tests {
         assert!(Extension::url_matches("https://example.com/page", "*://example.com/*"));
         assert!(Extension::url_matches("https://test.example.com/", "*://*.example.com/*"));
     }
+    
+    #[test]
+    fn test_match_patterns() {
+        let pattern = MatchPattern::parse("*://*.example.com/docs/*").unwrap();
+        assert!(pattern.matches("https://example.com/docs/intro"));
+        assert!(pattern.matches("http://a.b.example.com/docs/?q=1"));
+        assert!(!pattern.matches("https://badexample.com/docs/intro"));
+        assert!(!pattern.matches("https://example.com.evil.org/docs/"));
+        assert!(!pattern.matches("https://example.com/blog/docs/"));
+        assert!(!pattern.matches("ftp://example.com/docs/"));
+        
+        let pattern = MatchPattern::parse("https://example.com/*.html?*").unwrap();
+        assert!(pattern.matches("https://example.com/a/b.html?x=1"));
+        assert!(!pattern.matches("https://example.com/a/b.html"));
+        
+        assert!(MatchPattern::parse("<all_urls>").unwrap().matches("file:///home/a.txt"));
+        for invalid in ["example.com/*", "*://exa*mple.com/*", "chrome://settings/*", "https://example.com", "file://*/*"] {
+            assert!(MatchPattern::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+    
+    #[test]
+    fn test_scripts_to_inject() {
+        let mut mgr = ExtensionManager::new();
+        let script = |js: &str, run_at| ContentScript {
+            matches: vec!["https://*.example.com/*".to_string()],
+            exclude_matches: vec!["*://*/private/*".to_string()],
+            js: vec![js.to_string()],
+            css: vec![],
+            run_at,
+        };
+        let manifest = ExtensionManifest {
+            name: "Reader".to_string(),
+            version: "1.0.0".to_string(),
+            description: String::new(),
+            permissions: vec![],
+            content_scripts: vec![script("start.js", RunAt::DocumentStart), script("end.js", RunAt::DocumentEnd)],
+            background: None,
+            browser_action: None,
+            page_action: None,
+            declarative_net_request: vec![],
+        };
+        
+        let mut bad = manifest.clone();
+        bad.content_scripts[0].matches = vec!["example.com".to_string()];
+        assert!(matches!(mgr.load("bad", bad), Err(ExtensionError::InvalidManifest(_))));
+        
+        mgr.load("reader", manifest).unwrap();
+        mgr.add_resource("reader", "start.js", "started = true;");
+        
+        let start = mgr.scripts_to_inject("https://www.example.com/news", RunAt::DocumentStart);
+        assert_eq!(start, vec![ContentScriptSource {
+            extension_id: "reader".to_string(),
+            url: "extension://reader/start.js".to_string(),
+            source: "started = true;".to_string(),
+        }]);
+        // end.js was never packaged
+        assert!(mgr.scripts_to_inject("https://www.example.com/news", RunAt::DocumentEnd).is_empty());
+        assert!(mgr.scripts_to_inject("https://www.example.com/private/a", RunAt::DocumentStart).is_empty());
+        
+        mgr.set_enabled("reader", false);
+        assert!(mgr.scripts_to_inject("https://www.example.com/news", RunAt::DocumentStart).is_empty());
+    }
 }
@@ -25,6 +25,14 @@ impl FileEntry {
     pub fn extension(&self) -> Option<&str> {
         self.name.rsplit('.').next()
     }
+    
+    /// Body to upload this file with, read from disk as it is sent
+    pub fn upload_body(&self) -> Option<fos_net::UploadBody> {
+        if let Some(path) = &self.path {
+            return fos_net::UploadBody::from_file(path).ok();
+        }
+        self.content.clone().map(fos_net::UploadBody::from_bytes)
+    }
 }
 
 /// File list from input
@@ -4,6 +4,7 @@
 
 use std::collections::HashMap;
 use fos_dom::{Document, DomTree, NodeId};
+use crate::autofill::FormField;
 
 /// Form submission method
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -316,6 +317,99 @@ impl FormCollector {
             form_data.add_field(&name, &value);
         }
     }
+    
+    /// Fields of a form that autofill can fill: enabled, editable text
+    /// inputs, selects and textareas, with the label text the user sees
+    pub fn autofill_fields(tree: &DomTree, form_id: NodeId) -> Vec<FormField> {
+        let mut labels = HashMap::new();
+        Self::collect_labels(tree, form_id, &mut labels);
+        
+        let mut fields = Vec::new();
+        Self::collect_autofill_fields(tree, form_id, None, &labels, &mut fields);
+        fields
+    }
+    
+    /// Text of `<label for>` elements, by the id they point at
+    fn collect_labels(tree: &DomTree, node_id: NodeId, labels: &mut HashMap<String, String>) {
+        for (child_id, child_node) in tree.children(node_id) {
+            if let Some(elem) = child_node.as_element() {
+                if tree.resolve(elem.name.local).eq_ignore_ascii_case("label") {
+                    if let Some(target) = attribute(tree, elem, "for") {
+                        labels.insert(target.to_string(), text_of(tree, child_id));
+                    }
+                }
+            }
+            Self::collect_labels(tree, child_id, labels);
+        }
+    }
+    
+    fn collect_autofill_fields(
+        tree: &DomTree,
+        node_id: NodeId,
+        enclosing_label: Option<&str>,
+        labels: &HashMap<String, String>,
+        fields: &mut Vec<FormField>,
+    ) {
+        for (child_id, child_node) in tree.children(node_id) {
+            let Some(elem) = child_node.as_element() else { continue };
+            let tag = tree.resolve(elem.name.local).to_lowercase();
+            let attr = |name| attribute(tree, elem, name);
+            
+            if tag == "label" {
+                let text = text_of(tree, child_id);
+                Self::collect_autofill_fields(tree, child_id, Some(&text), labels, fields);
+                continue;
+            }
+            
+            let fillable = match tag.as_str() {
+                "input" => !matches!(
+                    attr("type").map(str::to_lowercase).as_deref(),
+                    Some("hidden" | "submit" | "image" | "button" | "reset" | "checkbox" | "radio" | "file" | "password")
+                ),
+                "select" | "textarea" => true,
+                _ => false,
+            };
+            if fillable && attr("disabled").is_none() && attr("readonly").is_none() {
+                let id = attr("id").map(String::from);
+                let label = id.as_ref()
+                    .and_then(|id| labels.get(id).cloned())
+                    .or_else(|| enclosing_label.map(String::from));
+                fields.push(FormField {
+                    element_id: child_id.0 as u64,
+                    name: attr("name").map(String::from),
+                    id,
+                    autocomplete: attr("autocomplete").map(String::from),
+                    field_type: attr("type").map(String::from),
+                    placeholder: attr("placeholder").map(String::from),
+                    label,
+                });
+            }
+            
+            Self::collect_autofill_fields(tree, child_id, enclosing_label, labels, fields);
+        }
+    }
+}
+
+fn attribute<'a>(tree: &DomTree, elem: &'a fos_dom::ElementData, name: &str) -> Option<&'a str> {
+    elem.attrs.iter()
+        .find(|a| tree.resolve(a.name.local).eq_ignore_ascii_case(name))
+        .map(|a| a.value.as_ref())
+}
+
+/// Whitespace-collapsed text of a node's descendants
+fn text_of(tree: &DomTree, node_id: NodeId) -> String {
+    fn collect(tree: &DomTree, node_id: NodeId, out: &mut String) {
+        for (child_id, child) in tree.children(node_id) {
+            if let Some(text) = child.as_text() {
+                out.push_str(text);
+                out.push(' ');
+            }
+            collect(tree, child_id, out);
+        }
+    }
+    let mut text = String::new();
+    collect(tree, node_id, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
 // URL encoding helper
@@ -359,4 +453,31 @@ mod tests {
         assert_eq!(urlencoding::encode("hello world"), "hello+world");
         assert_eq!(urlencoding::encode("a=b&c=d"), "a%3Db%26c%3Dd");
     }
+    
+    #[test]
+    fn test_autofill_fields() {
+        use crate::autofill::{AutofillFieldType, AutofillManager};
+        
+        let html = r#"<html><body><form>
+            <label for="fn">First name</label><input id="fn">
+            <label>E-mail <input name="contact"></label>
+            <input autocomplete="billing postal-code">
+            <input type="hidden" name="token"><input name="locked" disabled>
+        </form></body></html>"#;
+        let document = fos_html::parse(html);
+        let tree = document.tree();
+        let form = (0..tree.len() as u32).map(NodeId).find(|&id| {
+            tree.get(id).and_then(|n| n.as_element()).is_some_and(|e| tree.resolve(e.name.local) == "form")
+        }).unwrap();
+        
+        let manager = AutofillManager::new();
+        let types: Vec<_> = FormCollector::autofill_fields(tree, form).iter()
+            .map(|f| manager.detect_field_type(f))
+            .collect();
+        assert_eq!(types, [
+            Some(AutofillFieldType::GivenName),
+            Some(AutofillFieldType::Email),
+            Some(AutofillFieldType::PostalCode),
+        ]);
+    }
 }
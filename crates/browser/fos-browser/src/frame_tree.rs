@@ -0,0 +1,450 @@
+//! Frame Tree and Joint Session History
+//!
+//! The frames of a tab and the history they share. Every navigation that
+//! adds an entry, in the top-level document or in any iframe, takes the next
+//! step of the tab's history; back and forward move between steps, and each
+//! frame shows its last entry at or before the current step. Child frames
+//! belong to the entry of their parent that created them, so traversing the
+//! parent restores the iframes of that document with their own history.
+//!
+//! A new frame starts on an initial `about:blank` document that inherits the
+//! origin and CSP of its creator; navigating away from it replaces it instead
+//! of adding an entry. So does a new tab's top-level `about:blank`.
+//!
+//! The top-level frame's name is partitioned by site: navigating cross-site
+//! sets `window.name` aside and navigating back to the site restores it, so
+//! it cannot carry an identifier from one site to the next.
+
+use fos_security::{ContentSecurityPolicy, Origin, WindowNamePartition};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Frame identifier, unique within a tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FrameId(pub u64);
+
+/// Session history entry of one frame
+#[derive(Debug, Clone)]
+pub struct FrameEntry {
+    pub url: String,
+    /// Serialized origin of the document; `null` if opaque
+    pub origin: String,
+    /// CSP the document enforces
+    pub csp: Option<ContentSecurityPolicy>,
+    /// Step of the joint session history that created the entry
+    pub step: usize,
+    /// Frames created by the document
+    pub children: Vec<FrameId>,
+}
+
+#[derive(Debug, Clone)]
+struct Frame {
+    parent: Option<FrameId>,
+    /// Name for `target=` lookups (`<iframe name>`, `window.name`)
+    name: String,
+    entries: Vec<FrameEntry>,
+    /// Still on the initial about:blank document
+    initial: bool,
+}
+
+/// Frames of a tab and their joint session history
+#[derive(Debug, Clone)]
+pub struct FrameTree {
+    frames: HashMap<FrameId, Frame>,
+    root: FrameId,
+    next_id: u64,
+    current_step: usize,
+    /// Top-level names set aside by site
+    names: WindowNamePartition,
+}
+
+impl FrameTree {
+    /// Tree with a top-level document at `url`; `about:blank` is the
+    /// initial document, which the first navigation replaces
+    pub fn new(url: &str) -> Self {
+        let root = FrameId(0);
+        let entry = FrameEntry {
+            url: url.to_string(),
+            origin: origin_of(url),
+            csp: None,
+            step: 0,
+            children: Vec::new(),
+        };
+        let initial = url.eq_ignore_ascii_case("about:blank");
+        let frame = Frame { parent: None, name: String::new(), entries: vec![entry], initial };
+        Self {
+            frames: HashMap::from([(root, frame)]),
+            root,
+            next_id: 1,
+            current_step: 0,
+            names: WindowNamePartition::new(),
+        }
+    }
+
+    /// Tree whose top-level frame has the history of saved entries
+    pub fn from_entries(back: &[String], current: &str, forward: &[String]) -> Self {
+        let mut urls = back.iter().map(String::as_str).chain([current]).chain(forward.iter().map(String::as_str));
+        let mut tree = Self::new(urls.next().unwrap_or(current));
+        for url in urls {
+            tree.navigate(tree.root, url, None, None, false);
+        }
+        tree.go(-(forward.len() as i32));
+        tree
+    }
+
+    /// Top-level frame
+    pub fn root(&self) -> FrameId {
+        self.root
+    }
+
+    /// Add a child frame to the current document of `parent`
+    pub fn create_frame(&mut self, parent: FrameId, name: &str) -> Option<FrameId> {
+        let current_step = self.current_step;
+        let parent_entry = self.frames.get_mut(&parent)?.entries.iter_mut().rev().find(|e| e.step <= current_step)?;
+        let id = FrameId(self.next_id);
+        self.next_id += 1;
+        parent_entry.children.push(id);
+
+        // The initial about:blank document takes the creator's policies
+        let entry = FrameEntry {
+            url: "about:blank".to_string(),
+            origin: parent_entry.origin.clone(),
+            csp: parent_entry.csp.clone(),
+            step: parent_entry.step,
+            children: Vec::new(),
+        };
+        self.frames.insert(id, Frame { parent: Some(parent), name: name.to_string(), entries: vec![entry], initial: true });
+        Some(id)
+    }
+
+    /// Remove a frame and its history, e.g. when its iframe leaves the DOM
+    pub fn remove_frame(&mut self, frame: FrameId) {
+        if frame == self.root {
+            return;
+        }
+        let Some(removed) = self.frames.remove(&frame) else {
+            return;
+        };
+        if let Some(parent) = removed.parent.and_then(|p| self.frames.get_mut(&p)) {
+            for entry in &mut parent.entries {
+                entry.children.retain(|&c| c != frame);
+            }
+        }
+        self.collect_garbage();
+    }
+
+    /// Navigate `frame` to `url`. `csp` is the response's
+    /// Content-Security-Policy header; `initiator` is the frame whose
+    /// document started the navigation, whose origin and CSP an
+    /// `about:blank` document inherits. Returns false for unknown frames.
+    pub fn navigate(
+        &mut self,
+        frame: FrameId,
+        url: &str,
+        csp: Option<&str>,
+        initiator: Option<FrameId>,
+        replace: bool,
+    ) -> bool {
+        if !self.frames.contains_key(&frame) {
+            return false;
+        }
+
+        let (origin, csp) = if is_about_blank(url) {
+            // Fall back to the parent when there is no initiator
+            let creator = initiator.or_else(|| self.frames.get(&frame).and_then(|f| f.parent));
+            match creator.and_then(|c| self.current_entry(c)) {
+                Some(entry) => (entry.origin.clone(), entry.csp.clone()),
+                None => ("null".to_string(), None),
+            }
+        } else {
+            (origin_of(url), csp.map(ContentSecurityPolicy::parse))
+        };
+
+        let previous_origin = self.current_entry(frame).filter(|_| frame == self.root).map(|e| e.origin.clone());
+        if let Some(previous) = previous_origin {
+            self.isolate_name(&previous, &origin);
+        }
+
+        let current_step = self.current_step;
+        let initial = self.frames[&frame].initial;
+        if replace || initial {
+            let frame = self.frames.get_mut(&frame).unwrap();
+            frame.initial = false;
+            if let Some(entry) = frame.entries.iter_mut().rev().find(|e| e.step <= current_step) {
+                *entry = FrameEntry { url: url.to_string(), origin, csp, step: entry.step, children: Vec::new() };
+            }
+        } else {
+            // A new entry drops every entry after the current step, in all frames
+            for frame in self.frames.values_mut() {
+                frame.entries.retain(|e| e.step <= current_step);
+            }
+            self.current_step += 1;
+            let step = self.current_step;
+            let frame = self.frames.get_mut(&frame).unwrap();
+            frame.entries.push(FrameEntry { url: url.to_string(), origin, csp, step, children: Vec::new() });
+        }
+        self.collect_garbage();
+        true
+    }
+
+    /// Frame a link or form with `target` opened from `source` navigates;
+    /// `None` if a new top-level browsing context is needed
+    pub fn find_frame(&self, source: FrameId, target: &str) -> Option<FrameId> {
+        let source_frame = self.frames.get(&source)?;
+        match target.to_ascii_lowercase().as_str() {
+            "" | "_self" => return Some(source),
+            "_parent" => return Some(source_frame.parent.unwrap_or(source)),
+            "_top" => return Some(self.root),
+            "_blank" => return None,
+            _ => {}
+        }
+        // Names are matched case-sensitively, nearest the top first
+        self.active_frames().into_iter().find(|id| self.frames[id].name == target)
+    }
+
+    /// Navigate the frame `target` names from `source`; `None` if no frame
+    /// has that name
+    pub fn navigate_target(&mut self, source: FrameId, target: &str, url: &str, csp: Option<&str>) -> Option<FrameId> {
+        let frame = self.find_frame(source, target)?;
+        self.navigate(frame, url, csp, Some(source), false);
+        Some(frame)
+    }
+
+    /// Go back one step; the frames that changed document and their URLs
+    pub fn back(&mut self) -> Vec<(FrameId, String)> {
+        self.go(-1)
+    }
+
+    /// Go forward one step
+    pub fn forward(&mut self) -> Vec<(FrameId, String)> {
+        self.go(1)
+    }
+
+    /// Traverse `delta` steps; nothing happens if that leaves the history
+    pub fn go(&mut self, delta: i32) -> Vec<(FrameId, String)> {
+        let steps = self.steps();
+        let Some(index) = steps.iter().position(|&s| s >= self.current_step) else {
+            return Vec::new();
+        };
+        let target = index as i64 + delta as i64;
+        if delta == 0 || target < 0 || target as usize >= steps.len() {
+            return Vec::new();
+        }
+
+        let before: HashMap<FrameId, usize> = self
+            .active_frames()
+            .into_iter()
+            .filter_map(|id| Some((id, self.current_entry(id)?.step)))
+            .collect();
+        let previous_origin = self.current_entry(self.root).map(|e| e.origin.clone());
+        self.current_step = steps[target as usize];
+        if let (Some(previous), Some(origin)) = (previous_origin, self.origin(self.root).map(str::to_string)) {
+            self.isolate_name(&previous, &origin);
+        }
+
+        self.active_frames()
+            .into_iter()
+            .filter_map(|id| {
+                let entry = self.current_entry(id)?;
+                (before.get(&id) != Some(&entry.step)).then(|| (id, entry.url.clone()))
+            })
+            .collect()
+    }
+
+    /// Number of entries in the joint history (`history.length`)
+    pub fn length(&self) -> usize {
+        self.steps().len()
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.steps().first().is_some_and(|&s| s < self.current_step)
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.steps().last().is_some_and(|&s| s > self.current_step)
+    }
+
+    /// Current entry of a frame
+    pub fn current_entry(&self, frame: FrameId) -> Option<&FrameEntry> {
+        self.frames.get(&frame)?.entries.iter().rev().find(|e| e.step <= self.current_step)
+    }
+
+    pub fn current_url(&self, frame: FrameId) -> Option<&str> {
+        self.current_entry(frame).map(|e| e.url.as_str())
+    }
+
+    /// Serialized origin of a frame's document
+    pub fn origin(&self, frame: FrameId) -> Option<&str> {
+        self.current_entry(frame).map(|e| e.origin.as_str())
+    }
+
+    pub fn csp(&self, frame: FrameId) -> Option<&ContentSecurityPolicy> {
+        self.current_entry(frame)?.csp.as_ref()
+    }
+
+    pub fn parent(&self, frame: FrameId) -> Option<FrameId> {
+        self.frames.get(&frame)?.parent
+    }
+
+    pub fn name(&self, frame: FrameId) -> Option<&str> {
+        self.frames.get(&frame).map(|f| f.name.as_str())
+    }
+
+    /// Set `window.name`
+    pub fn set_name(&mut self, frame: FrameId, name: &str) {
+        if let Some(frame) = self.frames.get_mut(&frame) {
+            frame.name = name.to_string();
+        }
+    }
+
+    /// Frames of the current documents, parents before children
+    pub fn active_frames(&self) -> Vec<FrameId> {
+        let mut frames = Vec::new();
+        let mut queue = VecDeque::from([self.root]);
+        while let Some(id) = queue.pop_front() {
+            frames.push(id);
+            if let Some(entry) = self.current_entry(id) {
+                queue.extend(entry.children.iter().copied());
+            }
+        }
+        frames
+    }
+
+    /// Swap the top-level name when its document goes from one origin's
+    /// site to another
+    fn isolate_name(&mut self, from: &str, to: &str) {
+        let root = self.frames.get_mut(&self.root).unwrap();
+        root.name = self.names.navigate(&root.name, from, to);
+    }
+
+    /// Steps with an entry, in order
+    fn steps(&self) -> Vec<usize> {
+        let mut steps: Vec<usize> = self.frames.values().flat_map(|f| f.entries.iter().map(|e| e.step)).collect();
+        steps.sort_unstable();
+        steps.dedup();
+        steps
+    }
+
+    /// Drop frames no remaining entry refers to
+    fn collect_garbage(&mut self) {
+        let mut reachable = HashSet::new();
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            if let Some(frame) = self.frames.get(&id) {
+                stack.extend(frame.entries.iter().flat_map(|e| e.children.iter().copied()));
+            }
+        }
+        self.frames.retain(|id, _| reachable.contains(id));
+    }
+}
+
+fn is_about_blank(url: &str) -> bool {
+    let url = url.split(['#', '?']).next().unwrap_or("");
+    url.eq_ignore_ascii_case("about:blank") || url.eq_ignore_ascii_case("about:srcdoc")
+}
+
+fn origin_of(url: &str) -> String {
+    match Origin::from_url(url) {
+        Some(origin) => origin.serialize(),
+        None => "null".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subframe_history() {
+        let mut tree = FrameTree::new("https://a.com/");
+        let root = tree.root();
+        let ad = tree.create_frame(root, "ad").unwrap();
+        assert_eq!(tree.origin(ad), Some("https://a.com"));
+
+        // Leaving the initial about:blank does not add an entry
+        tree.navigate(ad, "https://b.com/1", None, None, false);
+        assert_eq!(tree.length(), 1);
+        tree.navigate(ad, "https://b.com/2", None, None, false);
+        assert_eq!(tree.length(), 2);
+
+        assert_eq!(tree.back(), vec![(ad, "https://b.com/1".to_string())]);
+        assert!(tree.back().is_empty());
+        assert_eq!(tree.forward(), vec![(ad, "https://b.com/2".to_string())]);
+
+        // A top-level navigation after going back prunes the iframe's
+        // forward entry and unloads it
+        tree.back();
+        tree.navigate(root, "https://c.com/", None, None, false);
+        assert_eq!(tree.length(), 2);
+        assert_eq!(tree.active_frames(), vec![root]);
+
+        // Going back restores the iframe with its document
+        assert_eq!(tree.back(), vec![(root, "https://a.com/".to_string()), (ad, "https://b.com/1".to_string())]);
+        assert!(tree.can_go_forward());
+    }
+
+    #[test]
+    fn test_targets_and_about_blank() {
+        let mut tree = FrameTree::new("https://a.com/");
+        let root = tree.root();
+        tree.navigate(root, "https://a.com/page", Some("script-src 'self'"), None, true);
+        let left = tree.create_frame(root, "left").unwrap();
+        let right = tree.create_frame(root, "right").unwrap();
+        assert!(tree.csp(left).is_some());
+
+        assert_eq!(tree.find_frame(left, "right"), Some(right));
+        assert_eq!(tree.find_frame(left, "Right"), None);
+        assert_eq!(tree.find_frame(left, "_parent"), Some(root));
+        assert_eq!(tree.find_frame(left, "_blank"), None);
+
+        assert_eq!(tree.navigate_target(left, "right", "https://b.com/", None), Some(right));
+        assert_eq!(tree.origin(right), Some("https://b.com"));
+        assert!(tree.csp(right).is_none());
+
+        // about:blank takes the initiator's origin and CSP
+        tree.navigate_target(root, "right", "about:blank", None);
+        assert_eq!(tree.origin(right), Some("https://a.com"));
+        assert!(tree.csp(right).is_some());
+        tree.remove_frame(right);
+        assert_eq!(tree.find_frame(left, "right"), None);
+    }
+
+    #[test]
+    fn test_top_level_entries() {
+        let mut blank = FrameTree::new("about:blank");
+        let root = blank.root();
+        blank.navigate(root, "https://a.com/", None, None, false);
+        assert_eq!(blank.length(), 1);
+        assert!(!blank.can_go_back());
+
+        let back = ["https://a.com/".to_string(), "https://b.com/".to_string()];
+        let mut tree = FrameTree::from_entries(&back, "https://c.com/", &["https://d.com/".to_string()]);
+        assert_eq!((tree.length(), tree.current_url(root)), (4, Some("https://c.com/")));
+        assert_eq!(tree.forward(), vec![(root, "https://d.com/".to_string())]);
+    }
+
+    #[test]
+    fn test_window_name_isolation() {
+        let mut tree = FrameTree::new("https://a.com/");
+        let root = tree.root();
+        tree.set_name(root, "uid=42");
+        tree.navigate(root, "https://www.a.com/next", None, None, false);
+        assert_eq!(tree.name(root), Some("uid=42"));
+
+        // Another site cannot read it, and its own name stays behind too
+        tree.navigate(root, "https://tracker.net/", None, None, false);
+        assert_eq!(tree.name(root), Some(""));
+        tree.set_name(root, "tracker");
+        assert_eq!(tree.back(), vec![(root, "https://www.a.com/next".to_string())]);
+        assert_eq!(tree.name(root), Some("uid=42"));
+        tree.forward();
+        assert_eq!(tree.name(root), Some("tracker"));
+
+        // Iframes keep their names across sites
+        let child = tree.create_frame(root, "ad").unwrap();
+        tree.navigate(child, "https://b.com/", None, None, false);
+        assert_eq!(tree.name(child), Some("ad"));
+    }
+}
@@ -0,0 +1,586 @@
+//! Nested Browsing Contexts
+//!
+//! The `<iframe>` elements of a page load as child frames, each a [`Page`]
+//! of its own with its own document and script realm. Frames are tracked
+//! in the tab's [`FrameTree`], which gives each document its origin and
+//! keeps its history: an iframe the history already has loads the document
+//! of its current entry rather than its `src`. A frame is rendered at the
+//! size of its iframe, narrowed to fit its parent, and composited into the
+//! parent by the renderer, deepest frames first.
+//!
+//! Every realm gets `window`, `parent` and `top` objects whose
+//! `postMessage(data, targetOrigin)` sends to that frame, and `frames`
+//! with its child frames under their iframes' names. A script cannot
+//! be re-entered while it runs, so messages are queued and delivered by
+//! [`Frames::dispatch`] once the sender's script is done, to the
+//! receiver's `message` listeners, and only if the receiver's origin is the
+//! one the sender asked for. `window.open(url, target)` is queued the same
+//! way, for the browser to route to the frame `target` names.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use fos_dom::Document;
+use fos_dom::url::Url;
+use fos_js::{Callback, HostApi, HostObject, HostValue, IntoJs, JsError};
+use fos_security::{Origin, TargetOrigin};
+use crate::frame_tree::{FrameId, FrameTree};
+use crate::js_runtime::PageJsRuntime;
+use crate::page::Page;
+use crate::renderer::{FrameContent, PageRenderer, frame_size, iframe_elements};
+
+/// Deepest nesting of frames below the top-level page
+pub const MAX_FRAME_DEPTH: usize = 4;
+
+/// Most frames a page may load, nested ones included
+pub const MAX_FRAMES: usize = 32;
+
+/// Rounds of queued messages per dispatch; messages sent by the last
+/// round's listeners wait for the next dispatch
+const MAX_DISPATCH_ROUNDS: usize = 8;
+
+/// An iframe of a document and what it loads
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameRequest {
+    /// Position among the document's iframes
+    pub index: usize,
+    /// `name` attribute, for `target=` lookups
+    pub name: String,
+    /// Resolved `src`; `about:srcdoc` with `srcdoc`, and `about:blank`
+    /// without either
+    pub url: String,
+    /// Inline document of `srcdoc`
+    pub srcdoc: Option<String>,
+    /// Size in CSS pixels
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Iframes of a document at `base_url`, in document order
+pub fn iframe_requests(document: &Document, base_url: &str) -> Vec<FrameRequest> {
+    let tree = document.tree();
+    let base = Url::parse(base_url).ok();
+    iframe_elements(tree).into_iter().enumerate().filter_map(|(index, id)| {
+        let element = tree.get(id)?.as_element()?;
+        let attr = |name: &str| {
+            element.attrs.iter()
+                .find(|a| tree.resolve(a.name.local).eq_ignore_ascii_case(name))
+                .map(|a| a.value.clone())
+        };
+        let srcdoc = attr("srcdoc");
+        let url = match (&srcdoc, attr("src").filter(|src| !src.trim().is_empty())) {
+            (Some(_), _) => "about:srcdoc".to_string(),
+            (None, Some(src)) => base.as_ref()
+                .and_then(|base| base.join(src.trim()).ok())
+                .map_or_else(|| src.trim().to_string(), |url| url.to_string()),
+            (None, None) => "about:blank".to_string(),
+        };
+        let (width, height) = frame_size(tree, element);
+        Some(FrameRequest { index, name: attr("name").unwrap_or_default(), url, srcdoc, width, height })
+    }).collect()
+}
+
+/// A loaded child frame
+#[derive(Debug)]
+pub struct ChildFrame {
+    pub id: FrameId,
+    pub parent: FrameId,
+    /// Position among the iframes of the parent document
+    pub index: usize,
+    /// The frame's document and realm
+    pub page: Page,
+    /// Size in CSS pixels
+    pub width: f32,
+    pub height: f32,
+    /// Pixels as last rendered
+    pub content: Option<FrameContent>,
+}
+
+/// A message waiting for its sender's script to finish
+#[derive(Debug)]
+struct Message {
+    source: FrameId,
+    target: FrameId,
+    data: HostValue,
+    target_origin: TargetOrigin,
+}
+
+/// A `window.open` waiting for the browser to route it
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenRequest {
+    /// Frame whose script called it
+    pub source: FrameId,
+    /// URL resolved against the source's document
+    pub url: String,
+    /// Frame name, `_self`, `_parent`, `_top` or `_blank`
+    pub target: String,
+}
+
+#[derive(Debug, Default)]
+struct MessageQueue {
+    messages: Vec<Message>,
+    opens: Vec<OpenRequest>,
+    /// `message` listeners by the frame whose window they were added to
+    listeners: Vec<(FrameId, Callback)>,
+}
+
+/// Child frames of the shown page and the messages between them
+#[derive(Debug)]
+pub struct Frames {
+    tree: FrameTree,
+    children: Vec<ChildFrame>,
+    queue: Arc<Mutex<MessageQueue>>,
+}
+
+impl Frames {
+    /// No child frames, for a top-level document at `url`
+    pub fn new(url: &str) -> Self {
+        Self {
+            tree: FrameTree::new(url),
+            children: Vec::new(),
+            queue: Arc::new(Mutex::new(MessageQueue::default())),
+        }
+    }
+    
+    /// The frame tree, which holds each frame's URL and origin
+    pub fn tree(&self) -> &FrameTree {
+        &self.tree
+    }
+    
+    /// URL of the top-level document
+    pub fn url(&self) -> &str {
+        self.tree.current_url(self.tree.root()).unwrap_or("")
+    }
+    
+    /// Loaded child frames, parents before children
+    pub fn children(&self) -> &[ChildFrame] {
+        &self.children
+    }
+    
+    pub fn child(&self, frame: FrameId) -> Option<&ChildFrame> {
+        self.children.iter().find(|child| child.id == frame)
+    }
+    
+    /// Load the iframes of `root`, and theirs, into `tree`, the history of
+    /// the tab showing `root`, with `fetch(url, referrer)` returning a
+    /// document's HTML or None if it may not load. Child frames run scripts
+    /// only if `root` does; `root` gets its messaging globals now, and its
+    /// own scripts are left for the caller to run.
+    pub fn load(&mut self, tree: &mut FrameTree, root: &mut Page, mut fetch: impl FnMut(&str, &str) -> Option<String>) {
+        self.children.clear();
+        self.queue = Arc::new(Mutex::new(MessageQueue::default()));
+        let scripts = root.js_runtime.is_some();
+        
+        // Parents are loaded before their children
+        let mut pending = VecDeque::from([(tree.root(), root.url.clone(), root.document(), 0)]);
+        while let Some((parent, parent_url, document, depth)) = pending.pop_front() {
+            if depth >= MAX_FRAME_DEPTH {
+                continue;
+            }
+            let Some(document) = document else { continue };
+            let requests = iframe_requests(&document.lock().unwrap(), &parent_url);
+            for request in requests {
+                if self.children.len() >= MAX_FRAMES {
+                    log::warn!("{} loads more than {} frames; ignoring the rest", root.url, MAX_FRAMES);
+                    break;
+                }
+                // The iframe at this position of the document's entry, or a
+                // new frame; frames keep their place even if they don't load
+                let existing = tree.current_entry(parent).and_then(|e| e.children.get(request.index).copied());
+                let id = match existing {
+                    Some(id) => id,
+                    None => {
+                        let Some(id) = tree.create_frame(parent, &request.name) else { continue };
+                        tree.navigate(id, &request.url, None, Some(parent), false);
+                        id
+                    }
+                };
+                let url = tree.current_url(id).unwrap_or(&request.url).to_string();
+                let srcdoc = request.srcdoc.as_ref().filter(|_| url == "about:srcdoc");
+                let html = match srcdoc {
+                    Some(srcdoc) => srcdoc.clone(),
+                    None if url == "about:blank" => String::new(),
+                    None => match fetch(&url, &parent_url) {
+                        Some(html) => html,
+                        None => continue,
+                    },
+                };
+                
+                let mut page = Page::from_html(&url, html);
+                if !scripts {
+                    page.js_runtime = None;
+                }
+                // srcdoc documents resolve URLs against their parent's
+                let base = if srcdoc.is_some() { parent_url.clone() } else { url };
+                pending.push_back((id, base, page.document(), depth + 1));
+                self.children.push(ChildFrame {
+                    id,
+                    parent,
+                    index: request.index,
+                    page,
+                    width: request.width,
+                    height: request.height,
+                    content: None,
+                });
+            }
+        }
+        log::info!("Loaded {} frames for {}", self.children.len(), root.url);
+        self.tree = tree.clone();
+        
+        // Every realm can reach every other once all exist
+        if let Some(runtime) = root.js_runtime.as_ref() {
+            runtime.install_host_api(&self.api(self.tree.root()));
+        }
+        for i in 0..self.children.len() {
+            if self.children[i].page.js_runtime.is_none() {
+                continue;
+            }
+            if let Err(e) = self.children[i].page.initialize_javascript() {
+                log::warn!("Failed to initialize JavaScript in frame {}: {}", self.children[i].page.url, e);
+                continue;
+            }
+            let api = self.api(self.children[i].id);
+            let page = &mut self.children[i].page;
+            if let Some(runtime) = page.js_runtime.as_ref() {
+                runtime.install_host_api(&api);
+            }
+            if let Err(e) = page.execute_scripts() {
+                log::warn!("Script error in frame {}: {}", page.url, e);
+            }
+        }
+    }
+    
+    /// Messaging globals of a frame's realm
+    fn api(&self, frame: FrameId) -> HostApi {
+        let parent = self.tree.parent(frame).unwrap_or(frame);
+        let top = self.tree.root();
+        
+        let queue = self.queue.clone();
+        let add_listener = move |kind: String, callback: Callback, _options: Option<HostValue>| {
+            if kind == "message" {
+                queue.lock().unwrap().listeners.push((frame, callback));
+            }
+            Ok(())
+        };
+        
+        let window = self.window(frame, frame)
+            .function("addEventListener", add_listener.clone())
+            .function("open", self.open(frame))
+            .object("parent", self.window(frame, parent))
+            .object("top", self.window(frame, top))
+            .object("frames", self.frames_of(frame));
+        HostApi::new()
+            .object("window", window)
+            .object("self", self.window(frame, frame))
+            .object("parent", self.window(frame, parent))
+            .object("top", self.window(frame, top))
+            .object("frames", self.frames_of(frame))
+            .function("addEventListener", add_listener)
+            .function("postMessage", self.post_message(frame, frame))
+            .function("open", self.open(frame))
+    }
+    
+    /// `window.open(url, target)` from `source`, which opens a new tab
+    /// unless `target` names a frame
+    fn open(&self, source: FrameId) -> impl Fn(Option<String>, Option<String>) -> Result<(), JsError> + Clone + Send + Sync + 'static {
+        // about:srcdoc and about:blank documents resolve against their parent's
+        let mut base_frame = source;
+        while self.tree.current_url(base_frame).is_some_and(|url| url.starts_with("about:")) {
+            match self.tree.parent(base_frame) {
+                Some(parent) => base_frame = parent,
+                None => break,
+            }
+        }
+        let base = self.tree.current_url(base_frame).and_then(|url| Url::parse(url).ok());
+        let queue = self.queue.clone();
+        move |url: Option<String>, target: Option<String>| {
+            let url = url.unwrap_or_else(|| "about:blank".to_string());
+            let url = match &base {
+                Some(base) => base.join(url.trim()).map_err(|_| JsError::Syntax(format!("Invalid URL '{}' in window.open", url)))?.to_string(),
+                None => url,
+            };
+            let target = target.filter(|t| !t.is_empty()).unwrap_or_else(|| "_blank".to_string());
+            queue.lock().unwrap().opens.push(OpenRequest { source, url, target });
+            Ok(())
+        }
+    }
+    
+    /// Take the `window.open` calls made since the last time, in order
+    pub fn take_open_requests(&self) -> Vec<OpenRequest> {
+        std::mem::take(&mut self.queue.lock().unwrap().opens)
+    }
+    
+    /// `frames` of a frame: its named child frames by name, and `length`
+    fn frames_of(&self, frame: FrameId) -> HostObject {
+        let children: Vec<FrameId> = self.children.iter().filter(|c| c.parent == frame).map(|c| c.id).collect();
+        let mut frames = HostObject::new().value("length", children.len() as f64);
+        for child in children {
+            match self.tree.name(child) {
+                Some(name) if !name.is_empty() && name != "length" => frames = frames.object(name, self.window(frame, child)),
+                _ => {}
+            }
+        }
+        frames
+    }
+    
+    /// The window of `target` as scripts in `source` reach it
+    fn window(&self, source: FrameId, target: FrameId) -> HostObject {
+        HostObject::new().function("postMessage", self.post_message(source, target))
+    }
+    
+    /// `postMessage` from `source` to `target`
+    fn post_message(&self, source: FrameId, target: FrameId) -> impl Fn(HostValue, Option<String>) -> Result<(), JsError> + Clone + Send + Sync + 'static {
+        let sender = self.tree.origin(source).and_then(Origin::from_url);
+        let queue = self.queue.clone();
+        move |data: HostValue, target_origin: Option<String>| {
+            let target_origin = target_origin.unwrap_or_else(|| "/".to_string());
+            let Some(target_origin) = TargetOrigin::parse(&target_origin, sender.as_ref()) else {
+                return Err(JsError::Syntax(format!("Invalid target origin '{}' in postMessage", target_origin)));
+            };
+            if has_function(&data) {
+                return Err(JsError::Runtime("DataCloneError: functions cannot be cloned".to_string()));
+            }
+            queue.lock().unwrap().messages.push(Message { source, target, data, target_origin });
+            Ok(())
+        }
+    }
+    
+    /// Realm of a frame; the top-level one is `root`'s
+    fn runtime<'a>(&'a self, frame: FrameId, root: &'a Page) -> Option<&'a PageJsRuntime> {
+        if frame == self.tree.root() {
+            return root.js_runtime.as_ref();
+        }
+        self.child(frame)?.page.js_runtime.as_ref()
+    }
+    
+    /// Deliver queued messages to `message` listeners; returns how many
+    /// were delivered. Messages whose target origin does not match the
+    /// receiver are dropped.
+    pub fn dispatch(&self, root: &Page) -> usize {
+        let mut delivered = 0;
+        for _ in 0..MAX_DISPATCH_ROUNDS {
+            let messages = std::mem::take(&mut self.queue.lock().unwrap().messages);
+            if messages.is_empty() {
+                break;
+            }
+            for message in messages {
+                let receiver = self.tree.origin(message.target).and_then(Origin::from_url);
+                if !message.target_origin.allows(receiver.as_ref()) {
+                    log::debug!("postMessage to {:?} blocked: target origin does not match", self.tree.current_url(message.target));
+                    continue;
+                }
+                let Some(runtime) = self.runtime(message.target, root) else { continue };
+                
+                let origin = self.tree.origin(message.source).unwrap_or("null");
+                let event = HostValue::object([
+                    ("type", "message".into_js()),
+                    ("data", message.data),
+                    ("origin", origin.into_js()),
+                ]);
+                let listeners: Vec<Callback> = self.queue.lock().unwrap().listeners.iter()
+                    .filter(|(frame, _)| *frame == message.target)
+                    .map(|(_, callback)| *callback)
+                    .collect();
+                for callback in listeners {
+                    if let Some(Err(e)) = runtime.call(&callback, std::slice::from_ref(&event)) {
+                        log::warn!("message listener failed: {}", e);
+                    }
+                }
+                delivered += 1;
+            }
+        }
+        delivered
+    }
+    
+    /// Run due timers in child frames
+    pub fn process_timers(&mut self) {
+        for child in &mut self.children {
+            if child.page.has_pending_timers() {
+                if let Err(e) = child.page.process_timers() {
+                    log::warn!("Timer processing error in frame {}: {}", child.page.url, e);
+                }
+            }
+        }
+    }
+    
+    /// Render every child frame, deepest first so each composites its own
+    /// children, and hand the renderer the top-level page's frames
+    pub fn render(&mut self, renderer: &mut PageRenderer) {
+        let viewport = renderer.viewport_size();
+        let ratio = renderer.css_pixel_ratio();
+        let parent_width = |frames: &Self, parent: FrameId| {
+            frames.child(parent).map_or(viewport.0 as f32 / ratio, |p| p.width)
+        };
+        
+        for i in (0..self.children.len()).rev() {
+            let contents = self.contents_of(self.children[i].id);
+            let child = &self.children[i];
+            let width = child.width.min(parent_width(self, child.parent)).max(1.0);
+            renderer.set_frame_contents(contents);
+            renderer.set_viewport((width * ratio) as u32, (child.height.max(1.0) * ratio) as u32);
+            let rendered = renderer.render_html(&child.page.html, &child.page.url, 0.0);
+            self.children[i].content = rendered.map(|r| FrameContent { pixels: r.pixels, width: r.width, height: r.height });
+        }
+        
+        renderer.set_viewport(viewport.0, viewport.1);
+        renderer.set_frame_contents(self.contents());
+    }
+    
+    /// Rendered child frames of the top-level page, by iframe in document
+    /// order
+    pub fn contents(&self) -> Vec<Option<FrameContent>> {
+        self.contents_of(self.tree.root())
+    }
+    
+    fn contents_of(&self, frame: FrameId) -> Vec<Option<FrameContent>> {
+        let mut contents = Vec::new();
+        for child in self.children.iter().filter(|c| c.parent == frame) {
+            if contents.len() <= child.index {
+                contents.resize(child.index + 1, None);
+            }
+            contents[child.index] = child.content.clone();
+        }
+        contents
+    }
+}
+
+impl Default for Frames {
+    fn default() -> Self {
+        Self::new("about:blank")
+    }
+}
+
+/// Whether a message holds a function, which cannot be cloned
+fn has_function(value: &HostValue) -> bool {
+    match value {
+        HostValue::Function(_) => true,
+        HostValue::Object(properties) => properties.iter().any(|(_, v)| has_function(v)),
+        HostValue::Array(items) => items.iter().any(has_function),
+        HostValue::Value(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn page(url: &str, html: &str) -> Page {
+        let mut page = Page::from_html(url, html.to_string());
+        page.initialize_javascript().unwrap();
+        page
+    }
+    
+    #[test]
+    fn test_iframe_requests() {
+        let page = page("https://a.com/dir/", r#"<body>
+            <iframe src="child.html" name="left" width="200" height="100"></iframe>
+            <iframe srcdoc="<p>hi</p>"></iframe>
+            <iframe></iframe>
+        </body>"#);
+        let requests = iframe_requests(&page.document().unwrap().lock().unwrap(), &page.url);
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].url, "https://a.com/dir/child.html");
+        assert_eq!((requests[0].name.as_str(), requests[0].width, requests[0].height), ("left", 200.0, 100.0));
+        assert_eq!((requests[1].url.as_str(), requests[1].srcdoc.as_deref()), ("about:srcdoc", Some("<p>hi</p>")));
+        assert_eq!((requests[2].url.as_str(), requests[2].width), ("about:blank", 300.0));
+    }
+    
+    #[test]
+    fn test_nested_frames_load() {
+        let mut root = page("https://a.com/", r#"<iframe src="https://b.com/"></iframe><iframe src="https://blocked.com/"></iframe>"#);
+        let mut fetched = Vec::new();
+        let mut frames = Frames::default();
+        let mut tree = FrameTree::new(&root.url);
+        frames.load(&mut tree, &mut root, |url, referrer| {
+            fetched.push((url.to_string(), referrer.to_string()));
+            match url {
+                "https://b.com/" => Some(r#"<iframe srcdoc="<p>inner</p>"></iframe>"#.to_string()),
+                _ => None,
+            }
+        });
+        assert_eq!(fetched, [
+            ("https://b.com/".to_string(), "https://a.com/".to_string()),
+            ("https://blocked.com/".to_string(), "https://a.com/".to_string()),
+        ]);
+        
+        let children = frames.children();
+        assert_eq!(children.len(), 2);
+        assert_eq!((children[0].parent, children[0].index), (frames.tree().root(), 0));
+        assert_eq!(children[1].parent, children[0].id);
+        assert_eq!(frames.tree().origin(children[0].id), Some("https://b.com"));
+        // srcdoc documents take their parent's origin
+        assert_eq!(frames.tree().origin(children[1].id), Some("https://b.com"));
+        assert!(children[1].page.document().is_some());
+        
+        // Frames render at their size, and the blocked one not at all
+        let mut renderer = PageRenderer::new(400, 300);
+        frames.render(&mut renderer);
+        let inner = frames.children()[1].content.as_ref().unwrap();
+        assert_eq!((inner.width, inner.height), (300, 150));
+        assert_eq!(renderer.viewport_size(), (400, 300));
+        let contents = frames.contents();
+        assert_eq!(contents.len(), 1);
+        assert!(contents[0].is_some());
+    }
+    
+    #[test]
+    fn test_post_message_target_origin() {
+        let mut root = page("https://a.com/", r#"<iframe src="https://b.com/" name="child"></iframe>"#);
+        let mut frames = Frames::default();
+        frames.load(&mut FrameTree::new(&root.url), &mut root, |_, _| Some(r#"<script>
+            function onMessage(e) { received = e.data.n; sender = e.origin; }
+            addEventListener('message', onMessage);
+            parent.postMessage('ready', 'https://a.com');
+            parent.postMessage('wrong', 'https://evil.com');
+        </script>"#.to_string()));
+        let runtime = root.js_runtime.as_ref().unwrap();
+        runtime.eval("function onMessage(e) { got = e.data; } window.addEventListener('message', onMessage);").unwrap();
+        
+        // Only the message for the parent's origin arrives, once scripts are done
+        assert_eq!(frames.dispatch(&root), 1);
+        assert_eq!(runtime.eval("got;").unwrap().as_string(), Some("ready"));
+        
+        runtime.eval("frames.child.postMessage({ n: 1 }, '*'); frames.child.postMessage('x', '/');").unwrap();
+        assert!(runtime.eval("frames.child.postMessage('x', 'nonsense');").is_err());
+        assert_eq!(frames.dispatch(&root), 1);
+        let child = frames.children()[0].page.js_runtime.as_ref().unwrap();
+        assert_eq!(child.eval("received;").unwrap().as_number(), Some(1.0));
+        assert_eq!(child.eval("sender;").unwrap().as_string(), Some("https://a.com"));
+    }
+    
+    #[test]
+    fn test_frames_load_from_history() {
+        let mut root = page("https://a.com/", r#"<iframe src="https://b.com/1" name="side"></iframe>"#);
+        let mut tree = FrameTree::new(&root.url);
+        let mut frames = Frames::default();
+        frames.load(&mut tree, &mut root, |_, _| Some(String::new()));
+        let side = frames.children()[0].id;
+        
+        // The frame shows its current entry, not the iframe's src
+        tree.navigate(side, "https://b.com/2", None, Some(tree.root()), false);
+        let mut fetched = Vec::new();
+        frames.load(&mut tree, &mut root, |url, _| {
+            fetched.push(url.to_string());
+            Some(String::new())
+        });
+        assert_eq!(fetched, ["https://b.com/2"]);
+        assert_eq!(frames.children()[0].id, side);
+        tree.back();
+        frames.load(&mut tree, &mut root, |_, _| Some(String::new()));
+        assert_eq!(frames.children()[0].page.url, "https://b.com/1");
+    }
+    
+    #[test]
+    fn test_window_open_requests() {
+        let mut root = page("https://a.com/dir/", r#"<iframe srcdoc="<script>window.open('x.html', 'side');</script>"></iframe>"#);
+        let mut frames = Frames::default();
+        frames.load(&mut FrameTree::new(&root.url), &mut root, |_, _| None);
+        root.js_runtime.as_ref().unwrap().eval("open('/popup');").unwrap();
+        
+        let child = frames.children()[0].id;
+        assert_eq!(frames.take_open_requests(), [
+            OpenRequest { source: child, url: "https://a.com/dir/x.html".to_string(), target: "side".to_string() },
+            OpenRequest { source: frames.tree().root(), url: "https://a.com/popup".to_string(), target: "_blank".to_string() },
+        ]);
+        assert!(frames.take_open_requests().is_empty());
+    }
+}
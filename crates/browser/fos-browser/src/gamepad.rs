@@ -1,6 +1,8 @@
 //! Gamepad API
 //!
-//! Game controller input.
+//! Game controller input and haptic feedback (`vibrationActuator`):
+//! dual-rumble effects drive the strong and weak grip motors, trigger-rumble
+//! effects the motors behind the triggers.
 
 use std::collections::HashMap;
 
@@ -44,6 +46,116 @@ pub enum StandardButton {
     Home,
 }
 
+/// Longest haptic effect a page may play, in milliseconds
+pub const MAX_EFFECT_DURATION: f64 = 5000.0;
+
+/// Haptic effect type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadHapticEffectType {
+    /// Strong (low frequency) and weak (high frequency) grip motors
+    DualRumble,
+    /// Grip motors plus the left and right trigger motors
+    TriggerRumble,
+}
+
+impl GamepadHapticEffectType {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "dual-rumble" => Some(Self::DualRumble),
+            "trigger-rumble" => Some(Self::TriggerRumble),
+            _ => None,
+        }
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DualRumble => "dual-rumble",
+            Self::TriggerRumble => "trigger-rumble",
+        }
+    }
+}
+
+/// Parameters of `playEffect()`; magnitudes are 0.0 to 1.0
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GamepadEffectParameters {
+    /// Milliseconds
+    pub duration: f64,
+    /// Milliseconds before the effect starts
+    pub start_delay: f64,
+    pub strong_magnitude: f64,
+    pub weak_magnitude: f64,
+    pub left_trigger: f64,
+    pub right_trigger: f64,
+}
+
+/// How a haptic effect ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadHapticsResult {
+    Complete,
+    /// Replaced by another effect or stopped by `reset()`
+    Preempted,
+}
+
+impl GamepadHapticsResult {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Complete => "complete",
+            Self::Preempted => "preempted",
+        }
+    }
+}
+
+/// Haptic effect error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HapticsError {
+    /// The gamepad has no actuator for this effect type
+    NotSupported,
+    /// A magnitude, delay or duration is out of range
+    InvalidParameters,
+    /// The gamepad is disconnected or the document is hidden
+    InvalidState,
+}
+
+impl std::fmt::Display for HapticsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotSupported => write!(f, "Haptic effect not supported"),
+            Self::InvalidParameters => write!(f, "Invalid haptic effect parameters"),
+            Self::InvalidState => write!(f, "Gamepad cannot play haptic effects"),
+        }
+    }
+}
+
+impl std::error::Error for HapticsError {}
+
+/// Controller motors, implemented by the platform input layer
+pub trait HapticsBackend: Send + std::fmt::Debug {
+    /// Start driving the motors of a gamepad
+    fn play(&mut self, index: u32, effect: GamepadHapticEffectType, params: &GamepadEffectParameters) -> bool;
+
+    /// Stop all motors of a gamepad
+    fn stop(&mut self, index: u32);
+}
+
+/// `gamepad.vibrationActuator`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GamepadHapticActuator {
+    /// Supported effect types (`effects`)
+    pub effects: Vec<GamepadHapticEffectType>,
+}
+
+/// Effect playing on a gamepad
+#[derive(Debug, Clone, Copy)]
+struct ActiveEffect {
+    id: u64,
+    effect: GamepadHapticEffectType,
+    params: GamepadEffectParameters,
+    /// Whether the motors were started after the start delay
+    started: bool,
+    start_at: f64,
+    end_at: f64,
+}
+
 /// Gamepad state
 #[derive(Debug, Clone)]
 pub struct Gamepad {
@@ -54,6 +166,8 @@ pub struct Gamepad {
     pub buttons: Vec<GamepadButton>,
     pub axes: Vec<f32>,
     pub timestamp: f64,
+    /// Rumble motors; `None` if the controller has none
+    pub vibration_actuator: Option<GamepadHapticActuator>,
 }
 
 impl Gamepad {
@@ -66,6 +180,7 @@ impl Gamepad {
             buttons: vec![GamepadButton::default(); 17],
             axes: vec![0.0; 4],
             timestamp: 0.0,
+            vibration_actuator: None,
         }
     }
     
@@ -95,6 +210,8 @@ impl Gamepad {
 pub enum GamepadEvent {
     Connected(u32),
     Disconnected(u32),
+    /// A `playEffect()` or `reset()` promise settled
+    HapticsSettled(u64, GamepadHapticsResult),
 }
 
 /// Gamepad manager
@@ -103,6 +220,10 @@ pub struct GamepadManager {
     gamepads: HashMap<u32, Gamepad>,
     pending_events: Vec<GamepadEvent>,
     next_index: u32,
+    haptics: Option<Box<dyn HapticsBackend>>,
+    /// Effect per gamepad index
+    effects: HashMap<u32, ActiveEffect>,
+    next_effect_id: u64,
 }
 
 impl GamepadManager {
@@ -138,8 +259,108 @@ impl GamepadManager {
         index
     }
     
+    /// Connect a gamepad with rumble motors
+    pub fn connect_with_haptics(&mut self, id: &str, effects: &[GamepadHapticEffectType]) -> u32 {
+        let index = self.connect(id);
+        if let Some(gamepad) = self.gamepads.get_mut(&index) {
+            gamepad.vibration_actuator = Some(GamepadHapticActuator { effects: effects.to_vec() });
+        }
+        index
+    }
+    
+    pub fn set_haptics_backend(&mut self, backend: Box<dyn HapticsBackend>) {
+        self.haptics = Some(backend);
+    }
+    
+    /// `vibrationActuator.playEffect(type, params)` at time `now` (ms);
+    /// the returned id settles through [`GamepadEvent::HapticsSettled`].
+    /// A new effect preempts the one playing.
+    pub fn play_effect(
+        &mut self,
+        index: u32,
+        effect: GamepadHapticEffectType,
+        params: GamepadEffectParameters,
+        document_visible: bool,
+        now: f64,
+    ) -> Result<u64, HapticsError> {
+        let gamepad = self.gamepads.get(&index).ok_or(HapticsError::InvalidState)?;
+        if !document_visible {
+            return Err(HapticsError::InvalidState);
+        }
+        let supported = gamepad.vibration_actuator.as_ref().is_some_and(|a| a.effects.contains(&effect));
+        if !supported || self.haptics.is_none() {
+            return Err(HapticsError::NotSupported);
+        }
+        let magnitudes = [params.strong_magnitude, params.weak_magnitude, params.left_trigger, params.right_trigger];
+        if params.duration < 0.0
+            || params.start_delay < 0.0
+            || params.duration + params.start_delay > MAX_EFFECT_DURATION
+            || magnitudes.iter().any(|m| !(0.0..=1.0).contains(m))
+        {
+            return Err(HapticsError::InvalidParameters);
+        }
+        
+        self.stop_effect(index, GamepadHapticsResult::Preempted);
+        let id = self.next_effect_id;
+        self.next_effect_id += 1;
+        let start_at = now + params.start_delay;
+        self.effects.insert(index, ActiveEffect { id, effect, params, started: false, start_at, end_at: start_at + params.duration });
+        self.tick(now);
+        Ok(id)
+    }
+    
+    /// `vibrationActuator.reset()`: stop the motors; the playing effect is
+    /// preempted and the reset itself completes
+    pub fn reset_haptics(&mut self, index: u32) -> Result<u64, HapticsError> {
+        if !self.gamepads.contains_key(&index) {
+            return Err(HapticsError::InvalidState);
+        }
+        self.stop_effect(index, GamepadHapticsResult::Preempted);
+        let id = self.next_effect_id;
+        self.next_effect_id += 1;
+        self.pending_events.push(GamepadEvent::HapticsSettled(id, GamepadHapticsResult::Complete));
+        Ok(id)
+    }
+    
+    /// Start delayed effects and finish elapsed ones
+    pub fn tick(&mut self, now: f64) {
+        let mut finished = Vec::new();
+        for (&index, active) in &mut self.effects {
+            if !active.started && now >= active.start_at {
+                active.started = true;
+                if let Some(backend) = self.haptics.as_mut() {
+                    backend.play(index, active.effect, &active.params);
+                }
+            }
+            if now >= active.end_at {
+                finished.push(index);
+            }
+        }
+        for index in finished {
+            self.stop_effect(index, GamepadHapticsResult::Complete);
+        }
+    }
+    
+    /// Stop every effect, e.g. when the page is hidden
+    pub fn stop_all_haptics(&mut self) {
+        let indices: Vec<u32> = self.effects.keys().copied().collect();
+        for index in indices {
+            self.stop_effect(index, GamepadHapticsResult::Preempted);
+        }
+    }
+    
+    fn stop_effect(&mut self, index: u32, result: GamepadHapticsResult) {
+        if let Some(active) = self.effects.remove(&index) {
+            if let Some(backend) = self.haptics.as_mut() {
+                backend.stop(index);
+            }
+            self.pending_events.push(GamepadEvent::HapticsSettled(active.id, result));
+        }
+    }
+    
     /// Disconnect a gamepad
     pub fn disconnect(&mut self, index: u32) -> bool {
+        self.stop_effect(index, GamepadHapticsResult::Preempted);
         if let Some(mut gamepad) = self.gamepads.remove(&index) {
             gamepad.connected = false;
             self.pending_events.push(GamepadEvent::Disconnected(index));
@@ -186,4 +407,49 @@ mod tests {
         mgr.disconnect(idx);
         assert!(mgr.get(idx).is_none());
     }
+    
+    #[derive(Debug, Default)]
+    struct Motors(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+    
+    impl HapticsBackend for Motors {
+        fn play(&mut self, index: u32, effect: GamepadHapticEffectType, params: &GamepadEffectParameters) -> bool {
+            self.0.lock().unwrap().push(format!("play {} {} {}", index, effect.as_str(), params.strong_magnitude));
+            true
+        }
+        
+        fn stop(&mut self, index: u32) {
+            self.0.lock().unwrap().push(format!("stop {}", index));
+        }
+    }
+    
+    #[test]
+    fn test_haptics() {
+        let mut mgr = GamepadManager::new();
+        let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        mgr.set_haptics_backend(Box::new(Motors(log.clone())));
+        let pad = mgr.connect_with_haptics("Xbox Controller", &[GamepadHapticEffectType::DualRumble]);
+        mgr.take_events();
+        
+        let rumble = GamepadEffectParameters { duration: 200.0, start_delay: 50.0, strong_magnitude: 1.0, ..Default::default() };
+        assert_eq!(
+            mgr.play_effect(pad, GamepadHapticEffectType::TriggerRumble, rumble, true, 0.0),
+            Err(HapticsError::NotSupported)
+        );
+        let too_strong = GamepadEffectParameters { strong_magnitude: 1.5, ..rumble };
+        assert_eq!(mgr.play_effect(pad, GamepadHapticEffectType::DualRumble, too_strong, true, 0.0), Err(HapticsError::InvalidParameters));
+        
+        // The motors start after the delay; a second effect preempts the first
+        let first = mgr.play_effect(pad, GamepadHapticEffectType::DualRumble, rumble, true, 0.0).unwrap();
+        assert!(log.lock().unwrap().is_empty());
+        mgr.tick(60.0);
+        let second = mgr.play_effect(pad, GamepadHapticEffectType::DualRumble, GamepadEffectParameters { duration: 100.0, ..Default::default() }, true, 100.0).unwrap();
+        mgr.tick(250.0);
+        assert_eq!(*log.lock().unwrap(), ["play 0 dual-rumble 1", "stop 0", "play 0 dual-rumble 0", "stop 0"]);
+        
+        let events = mgr.take_events();
+        assert!(matches!(events[..], [
+            GamepadEvent::HapticsSettled(a, GamepadHapticsResult::Preempted),
+            GamepadEvent::HapticsSettled(b, GamepadHapticsResult::Complete),
+        ] if a == first && b == second));
+    }
 }
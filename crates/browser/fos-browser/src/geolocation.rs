@@ -2,7 +2,6 @@
 //!
 //! Browser geolocation with permission handling.
 
-use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
 /// Geolocation position
@@ -13,7 +12,7 @@ pub struct Position {
 }
 
 /// Geographic coordinates
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Coordinates {
     pub latitude: f64,
     pub longitude: f64,
@@ -65,6 +64,12 @@ impl Default for GeolocationOptions {
 /// Watch ID for position watching
 pub type WatchId = u64;
 
+/// Platform location services, implemented by embedders (and by DevTools
+/// emulation)
+pub trait PositionProvider: Send + std::fmt::Debug {
+    fn position(&mut self, high_accuracy: bool) -> Result<Coordinates, GeolocationError>;
+}
+
 /// Geolocation manager
 #[derive(Debug)]
 pub struct GeolocationManager {
@@ -72,6 +77,7 @@ pub struct GeolocationManager {
     cached_position: Option<Position>,
     watches: Vec<(WatchId, GeolocationOptions)>,
     next_watch_id: WatchId,
+    provider: Option<Box<dyn PositionProvider>>,
 }
 
 impl Default for GeolocationManager {
@@ -87,9 +93,21 @@ impl GeolocationManager {
             cached_position: None,
             watches: Vec::new(),
             next_watch_id: 1,
+            provider: None,
         }
     }
     
+    pub fn set_provider(&mut self, provider: Box<dyn PositionProvider>) {
+        self.provider = Some(provider);
+        self.cached_position = None;
+    }
+    
+    /// Detach the current provider, e.g. to swap in an emulated one
+    pub fn take_provider(&mut self) -> Option<Box<dyn PositionProvider>> {
+        self.cached_position = None;
+        self.provider.take()
+    }
+    
     /// Request permission
     pub fn request_permission(&mut self) -> bool {
         // In a real browser, this would show a UI prompt
@@ -141,10 +159,30 @@ impl GeolocationManager {
         self.watches.retain(|(watch_id, _)| *watch_id != id);
     }
     
-    /// Query position from system (simulated)
-    fn query_position(&self, _options: GeolocationOptions) -> Result<Position, GeolocationError> {
-        // In a real implementation, query the system's location services
-        // For now, return a default position
+    /// Re-query the position for active watches; returns the callbacks to
+    /// run, one per watch, when the coordinates moved or the query failed
+    pub fn poll_watches(&mut self) -> Vec<(WatchId, Result<Position, GeolocationError>)> {
+        if self.watches.is_empty() || !self.permission_granted {
+            return Vec::new();
+        }
+        let high_accuracy = self.watches.iter().any(|(_, o)| o.enable_high_accuracy);
+        let options = GeolocationOptions { enable_high_accuracy: high_accuracy, ..Default::default() };
+        let result = self.query_position(options);
+        if let Ok(ref position) = result {
+            if self.cached_position.as_ref().is_some_and(|p| p.coords == position.coords) {
+                return Vec::new();
+            }
+            self.cached_position = Some(position.clone());
+        }
+        self.watches.iter().map(|(id, _)| (*id, result.clone())).collect()
+    }
+    
+    /// Query position from the provider, or a fixed position without one
+    fn query_position(&mut self, options: GeolocationOptions) -> Result<Position, GeolocationError> {
+        if let Some(provider) = self.provider.as_mut() {
+            let coords = provider.position(options.enable_high_accuracy)?;
+            return Ok(Position { coords, timestamp: Self::now() });
+        }
         Ok(Position {
             coords: Coordinates {
                 latitude: 0.0,
@@ -185,4 +223,38 @@ mod tests {
         let pos = geo.get_current_position(GeolocationOptions::default()).unwrap();
         assert!(pos.coords.accuracy > 0.0);
     }
+    
+    #[derive(Debug)]
+    struct Walking(f64);
+    
+    impl PositionProvider for Walking {
+        fn position(&mut self, _high_accuracy: bool) -> Result<Coordinates, GeolocationError> {
+            self.0 += 0.001;
+            Ok(Coordinates {
+                latitude: self.0,
+                longitude: 0.0,
+                accuracy: 5.0,
+                altitude: None,
+                altitude_accuracy: None,
+                heading: None,
+                speed: None,
+            })
+        }
+    }
+    
+    #[test]
+    fn test_watch_provider() {
+        let mut geo = GeolocationManager::new();
+        geo.set_provider(Box::new(Walking(48.0)));
+        geo.request_permission();
+        let id = geo.watch_position(GeolocationOptions::default()).unwrap();
+        
+        let updates = geo.poll_watches();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].0, id);
+        assert!((updates[0].1.as_ref().unwrap().coords.latitude - 48.001).abs() < 1e-9);
+        
+        geo.clear_watch(id);
+        assert!(geo.poll_watches().is_empty());
+    }
 }
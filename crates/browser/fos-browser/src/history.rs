@@ -3,11 +3,17 @@
 //! Integrates fos-js history and location APIs for browser navigation.
 
 use fos_js::{HistoryManager, LocationManager};
+use crate::navigation_api::{NavigateDisposition, Navigation, NavigationCommit};
+use crate::scroll_restoration::{ScrollRestoration, ScrollRestorer, ScrollSnapshot};
 
 /// Navigation manager for the browser
 pub struct NavigationIntegration {
     /// History manager
     history: HistoryManager,
+    /// `window.navigation`, kept in step with the History API
+    navigation: Navigation,
+    /// Saved scroll positions per entry
+    scroll: ScrollRestorer,
 }
 
 impl NavigationIntegration {
@@ -15,6 +21,8 @@ impl NavigationIntegration {
     pub fn new(initial_url: &str) -> Self {
         Self {
             history: HistoryManager::new(initial_url),
+            navigation: Navigation::new(initial_url),
+            scroll: ScrollRestorer::new(),
         }
     }
     
@@ -22,32 +30,100 @@ impl NavigationIntegration {
     
     /// Push new state to history
     pub fn push_state(&mut self, state: Option<String>, title: &str, url: &str) {
+        let previous = self.navigation.current_entry().key.clone();
+        if let Err(e) = self.navigation.push_state(state.clone(), Some(url)) {
+            log::warn!("pushState rejected: {}", e);
+            return;
+        }
         self.history.push_state(state, title.to_string(), url.to_string());
+        
+        // Forward entries were pruned; the new one keeps the scroll mode
+        let navigation = &self.navigation;
+        self.scroll.retain(|key| navigation.entries().iter().any(|e| e.key == key));
+        let current = self.navigation.current_entry().key.clone();
+        self.scroll.inherit(&previous, &current);
     }
     
     /// Replace current state
     pub fn replace_state(&mut self, state: Option<String>, title: &str, url: &str) {
+        if let Err(e) = self.navigation.replace_state(state.clone(), Some(url)) {
+            log::warn!("replaceState rejected: {}", e);
+            return;
+        }
         self.history.replace_state(state, title.to_string(), url.to_string());
     }
     
     /// Navigate back in history
     pub fn back(&mut self) -> Option<String> {
         self.history.back();
+        self.traverse_navigation(-1);
         Some(self.history.current().url.clone())
     }
     
     /// Navigate forward in history
     pub fn forward(&mut self) -> Option<String> {
         self.history.forward();
+        self.traverse_navigation(1);
         Some(self.history.current().url.clone())
     }
     
     /// Navigate by delta
     pub fn go(&mut self, delta: i32) -> Option<String> {
         self.history.go(delta);
+        self.traverse_navigation(delta);
         Some(self.history.current().url.clone())
     }
     
+    /// Mirror a History API traversal into the navigation entries
+    fn traverse_navigation(&mut self, delta: i32) -> Option<NavigationCommit> {
+        let event = self.navigation.go(delta).ok()?;
+        let commit = self.navigation.dispatch_result(event.navigation_id, NavigateDisposition::Proceed).ok();
+        let key = self.navigation.current_entry().key.clone();
+        self.scroll.begin_restore(&key);
+        commit
+    }
+    
+    // === Scroll restoration ===
+    
+    /// `history.scrollRestoration`
+    pub fn scroll_restoration(&self) -> ScrollRestoration {
+        self.scroll.mode(self.current_entry_key())
+    }
+    
+    /// Set `history.scrollRestoration`
+    pub fn set_scroll_restoration(&mut self, mode: ScrollRestoration) {
+        let key = self.navigation.current_entry().key.clone();
+        self.scroll.set_mode(&key, mode);
+    }
+    
+    /// Save the current entry's scroll positions before leaving it
+    pub fn record_scroll(&mut self, snapshot: ScrollSnapshot) {
+        let key = self.navigation.current_entry().key.clone();
+        self.scroll.save(&key, snapshot);
+    }
+    
+    /// Scroll positions to apply after a layout pass during a traversal
+    pub fn restore_scroll(&mut self, extents: &ScrollSnapshot, loaded: bool) -> Option<ScrollSnapshot> {
+        self.scroll.on_layout(extents, loaded)
+    }
+    
+    /// Stop a pending restore because the user scrolled
+    pub fn cancel_scroll_restore(&mut self) {
+        self.scroll.cancel();
+    }
+    
+    // === Navigation API ===
+    
+    /// `window.navigation`
+    pub fn navigation(&self) -> &Navigation {
+        &self.navigation
+    }
+    
+    /// `window.navigation`, for starting and committing navigations
+    pub fn navigation_mut(&mut self) -> &mut Navigation {
+        &mut self.navigation
+    }
+    
     /// Get history length
     pub fn history_length(&self) -> usize {
         self.history.length()
@@ -63,6 +139,11 @@ impl NavigationIntegration {
         self.history.current().state.as_deref()
     }
     
+    /// Get current Navigation API entry key
+    pub fn current_entry_key(&self) -> &str {
+        &self.navigation.current_entry().key
+    }
+    
     // === Location API ===
     
     /// Create a location manager for a URL
@@ -121,4 +202,39 @@ mod tests {
         nav.forward();
         assert_eq!(nav.current_url(), "/page2");
     }
+    
+    #[test]
+    fn test_navigation_api_follows_history() {
+        let mut nav = NavigationIntegration::new("https://example.com/");
+        let first_key = nav.current_entry_key().to_string();
+        nav.push_state(Some("1".into()), "", "/page1");
+        assert_eq!(nav.navigation().entries().len(), 2);
+        assert_eq!(nav.navigation().current_entry().url, "https://example.com/page1");
+        
+        nav.back();
+        assert_eq!(nav.current_entry_key(), first_key);
+        
+        // Cross-origin pushState is refused by both
+        nav.push_state(None, "", "https://other.example/");
+        assert_eq!(nav.history_length(), 2);
+    }
+    
+    #[test]
+    fn test_scroll_restoration() {
+        use crate::scroll_restoration::ScrollOffset;
+        
+        let mut nav = NavigationIntegration::new("https://example.com/");
+        nav.record_scroll(ScrollSnapshot::new(ScrollOffset::new(0.0, 900.0)));
+        nav.push_state(None, "", "/page1");
+        nav.back();
+        
+        let extents = ScrollSnapshot::new(ScrollOffset::new(0.0, 5000.0));
+        let restored = nav.restore_scroll(&extents, false).unwrap();
+        assert_eq!(restored.root.y, 900.0);
+        
+        nav.set_scroll_restoration(ScrollRestoration::Manual);
+        nav.forward();
+        nav.back();
+        assert!(nav.restore_scroll(&extents, false).is_none());
+    }
 }
@@ -0,0 +1,330 @@
+//! Browsing History Store
+//!
+//! Visited URLs kept across sessions, for URL bar autocomplete and the CSS
+//! `:visited` pseudo-class. Each URL is ranked by frecency: its visits,
+//! with typed ones counting double, weighted by how recent the last visit
+//! was.
+//!
+//! Pages only learn whether a link is visited through the colors it is
+//! painted with; the renderer applies no other `:visited` styles and
+//! scripts always see the unvisited style.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// A visited URL
+#[derive(Debug, Clone)]
+pub struct VisitedUrl {
+    pub url: String,
+    pub title: String,
+    /// Visits, typed ones included
+    pub visit_count: u32,
+    /// Visits the user typed into the URL bar
+    pub typed_count: u32,
+    /// Seconds since the Unix epoch
+    pub last_visit: u64,
+}
+
+impl VisitedUrl {
+    /// Visit count, typed visits counting double, weighted by the age of
+    /// the last visit
+    pub fn frecency(&self, now: u64) -> u32 {
+        let age = now.saturating_sub(self.last_visit);
+        let weight = match age {
+            a if a < 4 * DAY => 100,
+            a if a < 14 * DAY => 70,
+            a if a < 31 * DAY => 50,
+            a if a < 90 * DAY => 30,
+            _ => 10,
+        };
+        (self.visit_count + self.typed_count) * weight
+    }
+}
+
+/// URL bar autocomplete suggestion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub url: String,
+    pub title: String,
+    pub frecency: u32,
+    /// The input starts the URL, past its scheme and `www.`, rather than
+    /// appearing somewhere in it or its title
+    pub prefix: bool,
+}
+
+/// Shared set of visited URLs for matching `:visited`, updated as the
+/// history changes. Holds hashes only, not the URLs.
+#[derive(Debug, Clone, Default)]
+pub struct VisitedLinks(Arc<RwLock<HashSet<u64>>>);
+
+impl VisitedLinks {
+    /// Hash of a URL without its fragment
+    fn key(url: &str) -> u64 {
+        let url = url.split('#').next().unwrap_or_default();
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        hasher.finish()
+    }
+    
+    pub fn contains(&self, url: &str) -> bool {
+        self.0.read().unwrap().contains(&Self::key(url))
+    }
+    
+    fn insert(&self, url: &str) {
+        self.0.write().unwrap().insert(Self::key(url));
+    }
+    
+    fn remove(&self, url: &str) {
+        self.0.write().unwrap().remove(&Self::key(url));
+    }
+    
+    fn clear(&self) {
+        self.0.write().unwrap().clear();
+    }
+}
+
+/// URL without its scheme and `www.`, lowercase, as autocomplete matches it
+fn strip_url(url: &str) -> String {
+    let url = url.trim().to_lowercase();
+    let rest = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+    rest.strip_prefix("www.").unwrap_or(rest).to_string()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Browsing history, saved to disk
+#[derive(Debug, Default)]
+pub struct HistoryStore {
+    /// By URL without fragment
+    urls: HashMap<String, VisitedUrl>,
+    visited: VisitedLinks,
+    storage_path: Option<PathBuf>,
+}
+
+impl HistoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Create with persistence
+    pub fn with_storage(path: PathBuf) -> Self {
+        let mut store = Self::new();
+        store.storage_path = Some(path);
+        store.load();
+        store
+    }
+    
+    /// Record a visit to a loaded page; `typed` if the user entered its URL
+    pub fn record_visit(&mut self, url: &str, title: &str, typed: bool) {
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            return;
+        }
+        let url = url.split('#').next().unwrap_or_default();
+        let entry = self.urls.entry(url.to_string()).or_insert_with(|| VisitedUrl {
+            url: url.to_string(),
+            title: String::new(),
+            visit_count: 0,
+            typed_count: 0,
+            last_visit: 0,
+        });
+        entry.visit_count += 1;
+        if typed {
+            entry.typed_count += 1;
+        }
+        if !title.is_empty() {
+            entry.title = title.to_string();
+        }
+        entry.last_visit = now();
+        self.visited.insert(url);
+    }
+    
+    /// Visited URL, ignoring any fragment
+    pub fn get(&self, url: &str) -> Option<&VisitedUrl> {
+        self.urls.get(url.split('#').next().unwrap_or_default())
+    }
+    
+    /// Whether a link to `url` matches `:visited`
+    pub fn is_visited(&self, url: &str) -> bool {
+        self.visited.contains(url)
+    }
+    
+    /// Handle to the visited set for the renderer
+    pub fn visited_links(&self) -> VisitedLinks {
+        self.visited.clone()
+    }
+    
+    /// Suggestions for URL bar input: URLs the input starts first, then
+    /// URLs and titles containing it, each by frecency
+    pub fn suggest(&self, input: &str, limit: usize) -> Vec<Suggestion> {
+        let query = strip_url(input);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let now = now();
+        let mut suggestions: Vec<Suggestion> = self.urls.values()
+            .filter_map(|entry| {
+                let stripped = strip_url(&entry.url);
+                let prefix = stripped.starts_with(&query);
+                let matches = prefix || stripped.contains(&query) || entry.title.to_lowercase().contains(&query);
+                matches.then(|| Suggestion {
+                    url: entry.url.clone(),
+                    title: entry.title.clone(),
+                    frecency: entry.frecency(now),
+                    prefix,
+                })
+            })
+            .collect();
+        suggestions.sort_by(|a, b| b.prefix.cmp(&a.prefix).then(b.frecency.cmp(&a.frecency)).then(a.url.cmp(&b.url)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+    
+    /// Text to complete URL bar input with, inline after the cursor: the
+    /// rest of the host of the best URL the input starts, or the rest of
+    /// the URL once the input is past the host
+    pub fn inline_completion(&self, input: &str) -> Option<String> {
+        let query = strip_url(input);
+        let best = self.suggest(input, 1).into_iter().find(|s| s.prefix)?;
+        let stripped = strip_url(&best.url);
+        let host_end = stripped.find('/').map_or(stripped.len(), |i| i + 1);
+        let end = if query.len() < host_end { host_end } else { stripped.len() };
+        let completion = &stripped[query.len()..end];
+        (!completion.is_empty()).then(|| completion.to_string())
+    }
+    
+    /// Forget a URL
+    pub fn remove(&mut self, url: &str) -> bool {
+        let url = url.split('#').next().unwrap_or_default();
+        self.visited.remove(url);
+        self.urls.remove(url).is_some()
+    }
+    
+    /// Forget all history
+    pub fn clear(&mut self) {
+        self.urls.clear();
+        self.visited.clear();
+    }
+    
+    /// Save to disk
+    pub fn save(&self) {
+        let Some(path) = &self.storage_path else { return };
+        
+        let mut data = String::new();
+        for entry in self.urls.values() {
+            data.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                entry.url,
+                entry.title.replace(['\t', '\n'], " "),
+                entry.visit_count,
+                entry.typed_count,
+                entry.last_visit
+            ));
+        }
+        
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = fs::write(path, data) {
+            log::warn!("Failed to save history to {}: {}", path.display(), e);
+        }
+    }
+    
+    /// Load from disk
+    pub fn load(&mut self) {
+        let Some(path) = &self.storage_path else { return };
+        
+        let data = match fs::read_to_string(path) {
+            Ok(d) => d,
+            Err(_) => return,
+        };
+        
+        for line in data.lines() {
+            let parts: Vec<&str> = line.split('\t').collect();
+            let [url, title, visits, typed, last_visit] = parts[..] else { continue };
+            let entry = VisitedUrl {
+                url: url.to_string(),
+                title: title.to_string(),
+                visit_count: visits.parse().unwrap_or(1),
+                typed_count: typed.parse().unwrap_or(0),
+                last_visit: last_visit.parse().unwrap_or(0),
+            };
+            self.visited.insert(url);
+            self.urls.insert(url.to_string(), entry);
+        }
+    }
+    
+    pub fn len(&self) -> usize {
+        self.urls.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_frecency_suggestions() {
+        let mut history = HistoryStore::new();
+        history.record_visit("https://www.rust-lang.org/learn", "Learn Rust", false);
+        for _ in 0..3 {
+            history.record_visit("https://docs.rs/regex", "regex - Rust", false);
+        }
+        history.record_visit("https://docs.rs/serde", "serde", true);
+        history.record_visit("about:blank", "", true);
+        assert_eq!(history.len(), 3);
+        
+        // Frequent visits rank first, until they age
+        assert_eq!(history.suggest("docs", 5)[1].frecency, 200);
+        assert_eq!(history.suggest("docs", 5).iter().map(|s| s.url.as_str()).collect::<Vec<_>>(),
+            vec!["https://docs.rs/regex", "https://docs.rs/serde"]);
+        history.urls.get_mut("https://docs.rs/regex").unwrap().last_visit -= 100 * DAY;
+        assert_eq!(history.suggest("docs", 5)[0].url, "https://docs.rs/serde");
+        
+        // Prefix matches come before matches elsewhere in the URL or title
+        let rust = history.suggest("rust", 5);
+        assert_eq!(rust[0].url, "https://www.rust-lang.org/learn");
+        assert!(rust[0].prefix);
+        assert_eq!(rust[1].url, "https://docs.rs/regex");
+        
+        assert_eq!(history.inline_completion("ru").as_deref(), Some("st-lang.org/"));
+        assert_eq!(history.inline_completion("https://www.rust-lang.org/l").as_deref(), Some("earn"));
+        assert_eq!(history.inline_completion("learn"), None);
+    }
+    
+    #[test]
+    fn test_visited_links_and_persistence() {
+        let path = std::env::temp_dir().join(format!("fos-history-test-{}", std::process::id()));
+        let mut history = HistoryStore::with_storage(path.clone());
+        let visited = history.visited_links();
+        history.record_visit("https://example.com/page#intro", "Example", true);
+        assert!(visited.contains("https://example.com/page"));
+        assert!(visited.contains("https://example.com/page#other"));
+        assert!(!visited.contains("https://example.com/"));
+        history.save();
+        
+        let loaded = HistoryStore::with_storage(path.clone());
+        let entry = loaded.get("https://example.com/page").unwrap();
+        assert_eq!((entry.title.as_str(), entry.visit_count, entry.typed_count), ("Example", 1, 1));
+        assert!(loaded.is_visited("https://example.com/page"));
+        
+        history.remove("https://example.com/page");
+        assert!(!visited.contains("https://example.com/page"));
+        let _ = fs::remove_file(path);
+    }
+}
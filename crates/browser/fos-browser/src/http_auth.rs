@@ -0,0 +1,112 @@
+//! HTTP Authentication Prompts
+//!
+//! Answers 401 and 407 challenges for the network layer: saved HTTP
+//! credentials from the password manager first, then the browser's login
+//! prompt, saving what the user enters if they ask to.
+
+use std::sync::{Arc, Mutex};
+
+use fos_net::{AuthPrompt, AuthTarget, CredentialProvider, Credentials};
+
+use crate::passwords::PasswordManager;
+
+/// What the user entered in the login prompt
+#[derive(Debug, Clone)]
+pub struct LoginInput {
+    pub credentials: Credentials,
+    /// Save to the password manager
+    pub remember: bool,
+}
+
+/// Login prompt shown by the browser UI; `None` when the user cancels
+type LoginPrompt = Box<dyn Fn(&AuthPrompt) -> Option<LoginInput> + Send + Sync>;
+
+/// Credential provider backed by the password manager and a login prompt
+pub struct HttpAuthPrompter {
+    passwords: Arc<Mutex<PasswordManager>>,
+    prompt: Option<LoginPrompt>,
+}
+
+impl HttpAuthPrompter {
+    pub fn new(passwords: Arc<Mutex<PasswordManager>>) -> Self {
+        Self { passwords, prompt: None }
+    }
+    
+    /// Ask the user with `prompt` when no saved credentials apply
+    pub fn with_prompt(mut self, prompt: impl Fn(&AuthPrompt) -> Option<LoginInput> + Send + Sync + 'static) -> Self {
+        self.prompt = Some(Box::new(prompt));
+        self
+    }
+    
+    /// Most recently used saved credentials for the protection space
+    fn saved(&self, realm: &str) -> Option<Credentials> {
+        let mut passwords = self.passwords.lock().unwrap();
+        let (id, username) = passwords.find_for_origin(realm)
+            .into_iter()
+            .max_by_key(|c| c.last_used)
+            .map(|c| (c.id, c.username.clone()))?;
+        let password = passwords.get_password(id)?;
+        passwords.mark_used(id);
+        Some(Credentials { username, password })
+    }
+}
+
+impl CredentialProvider for HttpAuthPrompter {
+    fn credentials(&self, prompt: &AuthPrompt) -> Option<Credentials> {
+        let realm = signon_realm(prompt);
+        // Saved credentials that were just rejected go back to the user
+        if !prompt.retry {
+            if let Some(credentials) = self.saved(&realm) {
+                return Some(credentials);
+            }
+        }
+        
+        let input = (self.prompt.as_ref()?)(prompt)?;
+        if input.remember {
+            let mut passwords = self.passwords.lock().unwrap();
+            passwords.save_credential(&realm, &input.credentials.username, &input.credentials.password);
+            passwords.save();
+        }
+        Some(input.credentials)
+    }
+}
+
+/// Password manager key for HTTP credentials. Logins apply to a realm of
+/// an origin or proxy rather than to a page.
+pub fn signon_realm(prompt: &AuthPrompt) -> String {
+    match prompt.target {
+        AuthTarget::Server => format!("{}/{}", prompt.origin, prompt.realm),
+        AuthTarget::Proxy => format!("proxy://{}/{}", prompt.origin, prompt.realm),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fos_net::AuthScheme;
+    
+    #[test]
+    fn test_saved_credentials_then_prompt() {
+        let passwords = Arc::new(Mutex::new(PasswordManager::new()));
+        passwords.lock().unwrap().save_credential("https://intranet.example/Staff", "ada", "old");
+        let prompter = HttpAuthPrompter::new(passwords.clone()).with_prompt(|prompt| {
+            assert!(prompt.retry);
+            Some(LoginInput { credentials: Credentials::new("ada", "new"), remember: true })
+        });
+        
+        let mut prompt = AuthPrompt {
+            target: AuthTarget::Server,
+            origin: "https://intranet.example".to_string(),
+            realm: "Staff".to_string(),
+            scheme: AuthScheme::Digest,
+            retry: false,
+        };
+        assert_eq!(prompter.credentials(&prompt).unwrap().password, "old");
+        
+        prompt.retry = true;
+        assert_eq!(prompter.credentials(&prompt).unwrap().password, "new");
+        let passwords = passwords.lock().unwrap();
+        let saved = passwords.find_credential("https://intranet.example/Staff", "ada").unwrap();
+        assert_eq!(passwords.get_password(saved.id).as_deref(), Some("new"));
+    }
+}
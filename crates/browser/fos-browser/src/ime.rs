@@ -0,0 +1,240 @@
+//! Input Method Editors
+//!
+//! An IME composes text (CJK input, emoji pickers, dead keys) before
+//! handing it to the focused field. While it composes, the field shows
+//! the preedit text underlined at the caret, the page gets composition
+//! events, and the IME is told where the caret is so its candidate window
+//! sits beside the text.
+
+use std::ops::Range;
+
+use fos_js::CompositionEvent;
+
+/// What the platform's input method reports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImeInput {
+    /// The IME was turned on for the window
+    Enabled,
+    /// Text composed so far and the part of it the IME has selected, in
+    /// byte offsets; an empty selection is just the caret
+    Preedit(String, Option<Range<usize>>),
+    /// Text to insert into the field, ending the composition
+    Commit(String),
+    /// The IME was turned off
+    Disabled,
+}
+
+/// How part of the composition is underlined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnderlineStyle {
+    /// Text still being composed
+    Thin,
+    /// The clause being converted
+    Thick,
+}
+
+/// Text an IME is composing
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Composition {
+    pub text: String,
+    /// Selected part of `text`, in byte offsets
+    pub selection: Option<Range<usize>>,
+}
+
+impl Composition {
+    /// Underlines to draw, in character offsets into `text`: thin under
+    /// all of it and thick under the clause being converted
+    pub fn underlines(&self) -> Vec<(Range<usize>, UnderlineStyle)> {
+        let length = self.text.chars().count();
+        if length == 0 {
+            return Vec::new();
+        }
+        let mut underlines = vec![(0..length, UnderlineStyle::Thin)];
+        if let Some(selection) = self.char_selection().filter(|s| !s.is_empty()) {
+            underlines.push((selection, UnderlineStyle::Thick));
+        }
+        underlines
+    }
+    
+    /// Caret within the composition, in characters
+    pub fn caret(&self) -> usize {
+        self.char_selection()
+            .map(|selection| selection.end)
+            .unwrap_or_else(|| self.text.chars().count())
+    }
+    
+    fn char_selection(&self) -> Option<Range<usize>> {
+        let selection = self.selection.as_ref()?;
+        let chars = |byte: usize| self.text.get(..byte).map(|s| s.chars().count());
+        Some(chars(selection.start)?..chars(selection.end)?)
+    }
+}
+
+/// Platform IME for a window, implemented by the app layer
+pub trait ImeContext: Send + std::fmt::Debug {
+    /// Let the IME compose for the focused field, or stop it
+    fn set_allowed(&mut self, allowed: bool);
+    
+    /// Where the text being composed is, in window pixels
+    fn set_cursor_area(&mut self, x: i32, y: i32, width: u32, height: u32);
+}
+
+/// What an IME input changes: events for the page and text to insert
+#[derive(Debug, Default)]
+pub struct ImeUpdate {
+    pub events: Vec<CompositionEvent>,
+    /// Text committed to the focused field
+    pub commit: Option<String>,
+}
+
+/// IME state of a window
+#[derive(Debug, Default)]
+pub struct ImeManager {
+    context: Option<Box<dyn ImeContext>>,
+    /// An editable field has focus, so the IME may compose
+    allowed: bool,
+    composition: Option<Composition>,
+    /// Caret area last reported, so moves are only passed on once
+    cursor_area: Option<(i32, i32, u32, u32)>,
+}
+
+impl ImeManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn set_context(&mut self, context: Box<dyn ImeContext>) {
+        self.context = Some(context);
+    }
+    
+    /// Text being composed, if any
+    pub fn composition(&self) -> Option<&Composition> {
+        self.composition.as_ref()
+    }
+    
+    /// Whether composed text is showing. A composition the IME emptied,
+    /// as it does before committing or on cancel, stays open for the
+    /// commit but no longer takes keys.
+    pub fn is_composing(&self) -> bool {
+        self.composition.as_ref().is_some_and(|c| !c.text.is_empty())
+    }
+    
+    /// Turn the IME on while an editable field has focus. Losing focus
+    /// cancels any composition.
+    pub fn set_editable_focus(&mut self, focused: bool) -> ImeUpdate {
+        if focused == self.allowed {
+            return ImeUpdate::default();
+        }
+        self.allowed = focused;
+        self.cursor_area = None;
+        if let Some(context) = &mut self.context {
+            context.set_allowed(focused);
+        }
+        if focused { ImeUpdate::default() } else { self.cancel() }
+    }
+    
+    /// Handle input from the platform IME
+    pub fn handle(&mut self, input: ImeInput) -> ImeUpdate {
+        let mut update = ImeUpdate::default();
+        match input {
+            ImeInput::Enabled => {}
+            ImeInput::Preedit(text, selection) => {
+                if !self.allowed {
+                    return update;
+                }
+                if self.composition.is_none() {
+                    // Emptied preedits before a commit don't start anything
+                    if text.is_empty() {
+                        return update;
+                    }
+                    update.events.push(CompositionEvent::start(""));
+                }
+                if self.composition.as_ref().is_some_and(|c| c.text == text && c.selection == selection) {
+                    return update;
+                }
+                update.events.push(CompositionEvent::update(&text));
+                self.composition = Some(Composition { text, selection });
+            }
+            ImeInput::Commit(text) => {
+                if !self.allowed {
+                    return update;
+                }
+                // Commits without a preedit, like an emoji picker's, still
+                // get the full sequence
+                if self.composition.take().is_none() {
+                    update.events.push(CompositionEvent::start(""));
+                }
+                update.events.push(CompositionEvent::update(&text));
+                update.events.push(CompositionEvent::end(&text));
+                update.commit = Some(text);
+            }
+            ImeInput::Disabled => return self.cancel(),
+        }
+        update
+    }
+    
+    /// Drop the composition without inserting it
+    pub fn cancel(&mut self) -> ImeUpdate {
+        let mut update = ImeUpdate::default();
+        if self.composition.take().is_some() {
+            update.events.push(CompositionEvent::update(""));
+            update.events.push(CompositionEvent::end(""));
+        }
+        update
+    }
+    
+    /// Tell the IME where the focused field's caret is, in window pixels
+    pub fn report_cursor_area(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        let area = (x, y, width, height);
+        if !self.allowed || self.cursor_area == Some(area) {
+            return;
+        }
+        self.cursor_area = Some(area);
+        if let Some(context) = &mut self.context {
+            context.set_cursor_area(x, y, width, height);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fos_js::CompositionEventType;
+    
+    fn types(update: &ImeUpdate) -> Vec<CompositionEventType> {
+        update.events.iter().map(|e| e.event_type).collect()
+    }
+    
+    #[test]
+    fn test_composition_sequence() {
+        let mut ime = ImeManager::new();
+        assert!(ime.handle(ImeInput::Preedit("に".into(), None)).events.is_empty());
+        
+        ime.set_editable_focus(true);
+        let update = ime.handle(ImeInput::Preedit("に".into(), Some(3..3)));
+        assert_eq!(types(&update), vec![CompositionEventType::CompositionStart, CompositionEventType::CompositionUpdate]);
+        
+        let update = ime.handle(ImeInput::Preedit("日本ご".into(), Some(0..6)));
+        assert_eq!(update.events[0].data, "日本ご");
+        let composition = ime.composition().unwrap();
+        assert_eq!(composition.underlines(), vec![(0..3, UnderlineStyle::Thin), (0..2, UnderlineStyle::Thick)]);
+        assert_eq!(composition.caret(), 2);
+        
+        // The preedit empties just before the commit
+        assert_eq!(ime.handle(ImeInput::Preedit(String::new(), None)).events.len(), 1);
+        assert!(!ime.is_composing());
+        let update = ime.handle(ImeInput::Commit("日本語".into()));
+        assert_eq!(types(&update), vec![CompositionEventType::CompositionUpdate, CompositionEventType::CompositionEnd]);
+        assert_eq!(update.commit.as_deref(), Some("日本語"));
+        assert!(!ime.is_composing());
+        
+        // An emoji picker commits without composing first
+        let update = ime.handle(ImeInput::Commit("🎉".into()));
+        assert_eq!(update.events.len(), 3);
+        
+        ime.handle(ImeInput::Preedit("a".into(), None));
+        let update = ime.set_editable_focus(false);
+        assert_eq!(update.events.last().unwrap().data, "");
+        assert!(update.commit.is_none());
+    }
+}
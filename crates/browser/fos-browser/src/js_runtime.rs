@@ -2,10 +2,14 @@
 //!
 //! Integrates fos-js into the browser for script execution.
 
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use fos_dom::{Document, DomTree, NodeId};
-use fos_js::{JsContext, JsValue, JsError};
+use fos_js::{AppBadge, Callback, CookieManager, DeviceInfo, HostApi, HostValue, JsContext, JsValue, JsError};
+use fos_js::engine::{AllocationSampler, HeapSnapshot, ScriptCoverage};
+use fos_net::CookieJar;
 use fos_devtools::{Console, ConsoleMessage};
+use fos_security::StorageAccess;
 
 /// Script to execute
 #[derive(Debug, Clone)]
@@ -39,6 +43,11 @@ impl Default for ScriptType {
 pub struct PageJsRuntime {
     /// JavaScript context with DOM bindings
     context: Option<JsContext>,
+    /// Isolated worlds on the page, by name: one per extension with
+    /// content scripts
+    worlds: HashMap<String, JsContext>,
+    /// Host APIs of isolated worlds, installed as each world is created
+    world_apis: HashMap<String, HostApi>,
     /// Pending scripts to execute
     pending_scripts: Vec<Script>,
     /// Console for log output
@@ -47,6 +56,14 @@ pub struct PageJsRuntime {
     scripts_enabled: bool,
     /// Page URL for security context
     page_url: String,
+    /// Cookie jar behind document.cookie
+    cookie_jar: Arc<Mutex<CookieJar>>,
+    /// Storage access grants behind document.hasStorageAccess()
+    storage_access: Option<Arc<Mutex<StorageAccess>>>,
+    /// Data saver preference behind navigator.connection.saveData
+    save_data: bool,
+    /// Hardware values and time zone the page sees
+    device: DeviceInfo,
 }
 
 impl PageJsRuntime {
@@ -54,10 +71,16 @@ impl PageJsRuntime {
     pub fn new(page_url: &str) -> Self {
         Self {
             context: None,
+            worlds: HashMap::new(),
+            world_apis: HashMap::new(),
             pending_scripts: Vec::new(),
             console: Arc::new(Mutex::new(Console::new())),
             scripts_enabled: true,
             page_url: page_url.to_string(),
+            cookie_jar: Arc::new(Mutex::new(CookieJar::new())),
+            storage_access: None,
+            save_data: false,
+            device: DeviceInfo::default(),
         }
     }
     
@@ -67,13 +90,70 @@ impl PageJsRuntime {
             return Ok(());
         }
         
-        let context = JsContext::with_url(document, &self.page_url)?;
+        let mut cookies = CookieManager::new(self.cookie_jar.clone(), &self.page_url);
+        if let Some(ref grants) = self.storage_access {
+            cookies = cookies.with_storage_access(grants.clone());
+        }
+        let context = JsContext::with_cookies(document, &self.page_url, cookies)?;
+        context.set_save_data(self.save_data);
+        context.set_device_info(self.device.clone());
         self.context = Some(context);
+        self.worlds.clear();
         
         log::info!("JavaScript context initialized for {}", self.page_url);
         Ok(())
     }
     
+    /// Share a cookie jar with the network stack; takes effect on the next
+    /// `initialize`
+    pub fn set_cookie_jar(&mut self, jar: Arc<Mutex<CookieJar>>) {
+        self.cookie_jar = jar;
+    }
+    
+    /// Share the browser's storage access grants; takes effect on the next
+    /// `initialize`
+    pub fn set_storage_access(&mut self, grants: Arc<Mutex<StorageAccess>>) {
+        self.storage_access = Some(grants);
+    }
+    
+    /// The user clicked or typed in the page
+    pub fn notify_user_activation(&self) {
+        if let Some(ref context) = self.context {
+            context.notify_user_activation();
+        }
+    }
+    
+    /// Follow the data saver preference in navigator.connection.saveData
+    pub fn set_save_data(&mut self, enabled: bool) {
+        self.save_data = enabled;
+        if let Some(ref context) = self.context {
+            context.set_save_data(enabled);
+        }
+    }
+    
+    /// Set navigator.hardwareConcurrency, deviceMemory and the page's time
+    /// zone, as privacy settings allow
+    pub fn set_device_info(&mut self, device: DeviceInfo) {
+        if let Some(ref context) = self.context {
+            context.set_device_info(device.clone());
+        }
+        self.device = device;
+    }
+    
+    /// Badge the page set or cleared since the last call, if any
+    pub fn take_app_badge(&self) -> Option<AppBadge> {
+        self.context.as_ref().and_then(JsContext::take_app_badge)
+    }
+    
+    /// Move cookie warnings from the JS context to the console
+    fn flush_cookie_warnings(&self, context: &JsContext) {
+        let warnings = context.take_cookie_warnings();
+        let mut console = self.console.lock().unwrap();
+        for warning in warnings {
+            console.warn(&warning, Vec::new());
+        }
+    }
+    
     /// Extract scripts from the document
     pub fn extract_scripts(&mut self, document: &Document) {
         let tree = document.tree();
@@ -167,7 +247,7 @@ impl PageJsRuntime {
         for script in scripts {
             log::debug!("Executing inline script ({} bytes)", script.source.len());
             
-            match context.exec(&script.source) {
+            match context.exec_script(&script.source, &self.page_url) {
                 Ok(()) => {
                     log::debug!("Script executed successfully");
                 }
@@ -179,6 +259,7 @@ impl PageJsRuntime {
                     );
                 }
             }
+            self.flush_cookie_warnings(context);
         }
         
         // Remove executed inline scripts
@@ -195,7 +276,7 @@ impl PageJsRuntime {
         
         log::debug!("Executing external script from {} ({} bytes)", url, source.len());
         
-        match context.exec(source) {
+        match context.exec_script(source, url) {
             Ok(()) => {
                 log::debug!("External script executed successfully");
             }
@@ -207,6 +288,7 @@ impl PageJsRuntime {
                 );
             }
         }
+        self.flush_cookie_warnings(context);
         
         // Remove from pending
         self.pending_scripts.retain(|s| s.source_url.as_deref() != Some(url));
@@ -214,6 +296,63 @@ impl PageJsRuntime {
         Ok(())
     }
     
+    /// Execute a script in an isolated world, created on first use: it
+    /// shares the page's DOM but not its globals, so page scripts cannot
+    /// read or tamper with its variables and functions
+    pub fn execute_in_world(&mut self, world: &str, source: &str, url: &str) -> Result<(), JsError> {
+        let Some(ref context) = self.context else {
+            return Ok(());
+        };
+        
+        if !self.worlds.contains_key(world) {
+            let isolated = context.create_isolated_world()?;
+            if let Some(api) = self.world_apis.get(world) {
+                isolated.install_host_api(api);
+            }
+            self.worlds.insert(world.to_string(), isolated);
+            log::debug!("Created isolated world {} for {}", world, self.page_url);
+        }
+        let context = &self.worlds[world];
+        
+        log::debug!("Executing {} in world {} ({} bytes)", url, world, source.len());
+        
+        if let Err(e) = context.exec_script(source, url) {
+            log::error!("Script error in world {} ({}): {}", world, url, e);
+            self.console.lock().unwrap().error(
+                &format!("Script error ({}): {}", url, e),
+                Vec::new(),
+            );
+        }
+        self.flush_cookie_warnings(context);
+        
+        Ok(())
+    }
+    
+    /// Install embedder globals into the page's context
+    pub fn install_host_api(&self, api: &HostApi) {
+        if let Some(ref context) = self.context {
+            context.install_host_api(api);
+        }
+    }
+    
+    /// Set the host API of an isolated world, such as an extension's
+    /// `chrome` object for its content scripts; installed now if the world
+    /// exists, otherwise when it is created
+    pub fn set_world_api(&mut self, world: &str, api: HostApi) {
+        if let Some(context) = self.worlds.get(world) {
+            context.install_host_api(&api);
+        }
+        self.world_apis.insert(world.to_string(), api);
+    }
+    
+    /// Call a callback in whichever of the page's realms it belongs to;
+    /// None if it belongs to none of them
+    pub fn call(&self, callback: &Callback, args: &[HostValue]) -> Option<Result<HostValue, JsError>> {
+        self.context.iter().chain(self.worlds.values())
+            .find(|context| context.owns(callback))
+            .map(|context| context.call(callback, args))
+    }
+    
     /// Execute arbitrary JavaScript code
     pub fn eval(&self, code: &str) -> Result<JsValue, JsError> {
         let Some(ref context) = self.context else {
@@ -223,18 +362,52 @@ impl PageJsRuntime {
         context.eval(code)
     }
     
-    /// Process pending timers (setTimeout, setInterval)
+    /// Process pending timers (setTimeout, setInterval) of the page and
+    /// its isolated worlds
     pub fn process_timers(&self) -> Result<(), JsError> {
         let Some(ref context) = self.context else {
             return Ok(());
         };
         
-        context.process_timers()
+        context.process_timers()?;
+        for world in self.worlds.values() {
+            world.process_timers()?;
+        }
+        Ok(())
     }
     
     /// Check if there are pending timers
     pub fn has_pending_timers(&self) -> bool {
-        self.context.as_ref().map(|c| c.has_pending_timers()).unwrap_or(false)
+        self.context.iter().chain(self.worlds.values()).any(|c| c.has_pending_timers())
+    }
+    
+    /// Snapshot the page's JS heap
+    pub fn heap_snapshot(&self) -> Option<HeapSnapshot> {
+        self.context.as_ref().map(|c| c.heap_snapshot())
+    }
+    
+    /// Start sampling JS allocations, about one per `interval` bytes
+    pub fn start_allocation_sampling(&self, interval: usize) {
+        if let Some(ref context) = self.context {
+            context.start_allocation_sampling(interval);
+        }
+    }
+    
+    /// Stop sampling JS allocations and return the samples
+    pub fn stop_allocation_sampling(&self) -> Option<AllocationSampler> {
+        self.context.as_ref().and_then(|c| c.stop_allocation_sampling())
+    }
+    
+    /// Start counting which functions of the scripts run from now on execute
+    pub fn start_coverage(&self) {
+        if let Some(ref context) = self.context {
+            context.start_coverage();
+        }
+    }
+    
+    /// Stop counting and return the coverage of each script run meanwhile
+    pub fn stop_coverage(&self) -> Vec<ScriptCoverage> {
+        self.context.as_ref().and_then(|c| c.stop_coverage()).unwrap_or_default()
     }
     
     /// Get pending external script URLs
@@ -284,4 +457,35 @@ mod tests {
         runtime.set_scripts_enabled(false);
         assert!(!runtime.scripts_enabled());
     }
+    
+    #[test]
+    fn test_save_data_reaches_context() {
+        let mut runtime = PageJsRuntime::new("https://example.com");
+        runtime.set_save_data(true);
+        runtime.initialize(Arc::new(Mutex::new(Document::new("https://example.com")))).unwrap();
+        assert!(runtime.context.as_ref().unwrap().save_data());
+        runtime.set_save_data(false);
+        assert!(!runtime.context.as_ref().unwrap().save_data());
+        
+        runtime.set_device_info(DeviceInfo { hardware_concurrency: 4, ..DeviceInfo::default() });
+        assert_eq!(runtime.context.as_ref().unwrap().device_info().hardware_concurrency, 4);
+    }
+    
+    #[test]
+    fn test_isolated_world_scripts() {
+        let mut runtime = PageJsRuntime::new("https://example.com");
+        runtime.initialize(Arc::new(Mutex::new(Document::new("https://example.com")))).unwrap();
+        runtime.eval("function report(x) { return 'stolen'; }").unwrap();
+        
+        runtime.execute_in_world("ext", "seen = typeof report;", "extension://ext/content.js").unwrap();
+        runtime.execute_in_world("ext", "function report(x) { return x; }", "extension://ext/content.js").unwrap();
+        let world = &runtime.worlds["ext"];
+        assert_eq!(world.eval("seen;").unwrap().as_string(), Some("undefined"));
+        assert_eq!(world.eval("report('ok');").unwrap().as_string(), Some("ok"));
+        assert_eq!(runtime.eval("report('ok');").unwrap().as_string(), Some("stolen"));
+        
+        // A syntax error is reported, not returned
+        runtime.execute_in_world("ext", "function (", "extension://ext/broken.js").unwrap();
+        assert_eq!(runtime.console_messages().len(), 1);
+    }
 }
@@ -0,0 +1,326 @@
+//! Keyboard Lock and Keyboard Map APIs
+//!
+//! `navigator.keyboard.lock(codes)` lets a fullscreen page receive keys the
+//! browser or OS would otherwise handle, such as Escape, Alt+Tab or the
+//! Meta key. The lock only takes effect while the page is fullscreen; with
+//! Escape locked the user exits fullscreen by holding it down instead.
+//!
+//! `navigator.keyboard.getLayoutMap()` maps physical key codes to the
+//! characters they produce on the user's layout, so games can show "Press Z"
+//! for `KeyW` on an AZERTY keyboard.
+
+use std::collections::{HashMap, HashSet};
+
+/// How long Escape must be held to exit fullscreen while it is locked (ms)
+pub const ESCAPE_HOLD_MS: f64 = 2000.0;
+
+/// Keyboard API error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyboardError {
+    /// `lock()` got a string that is not a `KeyboardEvent.code` value
+    InvalidAccess(String),
+    /// Called from a nested browsing context or a hidden document
+    InvalidState,
+    /// Called from an insecure context
+    Security,
+}
+
+impl std::fmt::Display for KeyboardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidAccess(code) => write!(f, "Invalid key code: {}", code),
+            Self::InvalidState => write!(f, "Keyboard API only available in active top-level documents"),
+            Self::Security => write!(f, "Keyboard API requires a secure context"),
+        }
+    }
+}
+
+impl std::error::Error for KeyboardError {}
+
+/// Where a key press goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRouting {
+    /// Browser shortcuts first, then the page
+    Default,
+    /// Locked: only the page sees it
+    Page,
+    /// Escape was held long enough; leave fullscreen
+    ExitFullscreen,
+}
+
+/// `KeyboardLayoutMap`: physical key code to the character it produces
+/// without modifiers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyboardLayoutMap {
+    entries: HashMap<String, String>,
+}
+
+impl KeyboardLayoutMap {
+    /// Built-in layout by name: `us`, `de` (QWERTZ) or `fr` (AZERTY)
+    pub fn builtin(name: &str) -> Option<Self> {
+        let mut entries: HashMap<String, String> = HashMap::new();
+        for c in 'a'..='z' {
+            entries.insert(format!("Key{}", c.to_ascii_uppercase()), c.to_string());
+        }
+        for d in '0'..='9' {
+            entries.insert(format!("Digit{}", d), d.to_string());
+        }
+        let overrides: &[(&str, &str)] = match name {
+            "us" => &[
+                ("Minus", "-"), ("Equal", "="), ("BracketLeft", "["), ("BracketRight", "]"),
+                ("Backslash", "\\"), ("Semicolon", ";"), ("Quote", "'"), ("Backquote", "`"),
+                ("Comma", ","), ("Period", "."), ("Slash", "/"),
+            ],
+            "de" => &[
+                ("KeyY", "z"), ("KeyZ", "y"), ("Minus", "ß"), ("Equal", "´"),
+                ("BracketLeft", "ü"), ("BracketRight", "+"), ("Backslash", "#"), ("Semicolon", "ö"),
+                ("Quote", "ä"), ("Backquote", "^"), ("Comma", ","), ("Period", "."),
+                ("Slash", "-"), ("IntlBackslash", "<"),
+            ],
+            "fr" => &[
+                ("KeyQ", "a"), ("KeyA", "q"), ("KeyW", "z"), ("KeyZ", "w"), ("KeyM", ","),
+                ("Digit1", "&"), ("Digit2", "é"), ("Digit3", "\""), ("Digit4", "'"), ("Digit5", "("),
+                ("Digit6", "-"), ("Digit7", "è"), ("Digit8", "_"), ("Digit9", "ç"), ("Digit0", "à"),
+                ("Minus", ")"), ("Equal", "="), ("BracketLeft", "^"), ("BracketRight", "$"),
+                ("Backslash", "*"), ("Semicolon", "m"), ("Quote", "ù"), ("Backquote", "²"),
+                ("Comma", ";"), ("Period", ":"), ("Slash", "!"), ("IntlBackslash", "<"),
+            ],
+            _ => return None,
+        };
+        for (code, key) in overrides {
+            entries.insert(code.to_string(), key.to_string());
+        }
+        Some(Self { entries })
+    }
+
+    /// `get(code)`
+    pub fn get(&self, code: &str) -> Option<&str> {
+        self.entries.get(code).map(String::as_str)
+    }
+
+    /// `has(code)`
+    pub fn has(&self, code: &str) -> bool {
+        self.entries.contains_key(code)
+    }
+
+    /// `size`
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(code, key)| (code.as_str(), key.as_str()))
+    }
+}
+
+/// `navigator.keyboard` of a top-level document
+#[derive(Debug)]
+pub struct KeyboardManager {
+    /// Locked codes; empty means every key
+    locked: Option<HashSet<String>>,
+    /// When a locked Escape went down
+    escape_down_at: Option<f64>,
+    layout: KeyboardLayoutMap,
+}
+
+impl Default for KeyboardManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyboardManager {
+    pub fn new() -> Self {
+        Self {
+            locked: None,
+            escape_down_at: None,
+            layout: KeyboardLayoutMap::builtin("us").unwrap_or_default(),
+        }
+    }
+
+    /// `navigator.keyboard.lock(codes)`; no codes locks every key. A new
+    /// call replaces the previous set.
+    pub fn lock(&mut self, codes: &[&str], top_level: bool, visible: bool) -> Result<(), KeyboardError> {
+        if !top_level || !visible {
+            return Err(KeyboardError::InvalidState);
+        }
+        if let Some(code) = codes.iter().find(|code| !is_valid_code(code)) {
+            return Err(KeyboardError::InvalidAccess(code.to_string()));
+        }
+        self.locked = Some(codes.iter().map(|code| code.to_string()).collect());
+        self.escape_down_at = None;
+        Ok(())
+    }
+
+    /// `navigator.keyboard.unlock()`; also on navigation or when the
+    /// document is hidden
+    pub fn unlock(&mut self) {
+        self.locked = None;
+        self.escape_down_at = None;
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.is_some()
+    }
+
+    /// Whether the page gets `code` before the browser; the lock only
+    /// applies in fullscreen
+    pub fn captures(&self, code: &str, fullscreen: bool) -> bool {
+        match &self.locked {
+            Some(codes) => fullscreen && (codes.is_empty() || codes.contains(code)),
+            None => false,
+        }
+    }
+
+    /// Key pressed at time `now` (ms), including auto-repeats
+    pub fn key_down(&mut self, code: &str, fullscreen: bool, now: f64) -> KeyRouting {
+        if !self.captures(code, fullscreen) {
+            return KeyRouting::Default;
+        }
+        if code == "Escape" {
+            let down_at = *self.escape_down_at.get_or_insert(now);
+            if now - down_at >= ESCAPE_HOLD_MS {
+                self.escape_down_at = None;
+                return KeyRouting::ExitFullscreen;
+            }
+        }
+        KeyRouting::Page
+    }
+
+    /// Key released
+    pub fn key_up(&mut self, code: &str) {
+        if code == "Escape" {
+            self.escape_down_at = None;
+        }
+    }
+
+    /// Escape still held at `now` without a repeat; true once it has been
+    /// held long enough to exit fullscreen
+    pub fn escape_held(&mut self, now: f64) -> bool {
+        match self.escape_down_at {
+            Some(down_at) if now - down_at >= ESCAPE_HOLD_MS => {
+                self.escape_down_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Switch to a built-in layout when the OS layout changes
+    pub fn set_layout(&mut self, name: &str) -> bool {
+        match KeyboardLayoutMap::builtin(name) {
+            Some(layout) => {
+                self.layout = layout;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Learn from a key event pressed without modifiers, for layouts
+    /// without a built-in table
+    pub fn record_key(&mut self, code: &str, key: &str) {
+        let mut chars = key.chars();
+        let printable = matches!((chars.next(), chars.next()), (Some(c), None) if !c.is_control() && !c.is_whitespace());
+        if printable && is_valid_code(code) {
+            self.layout.entries.insert(code.to_string(), key.to_lowercase());
+        }
+    }
+
+    /// `navigator.keyboard.getLayoutMap()`
+    pub fn get_layout_map(&self, top_level: bool, secure: bool) -> Result<KeyboardLayoutMap, KeyboardError> {
+        if !secure {
+            return Err(KeyboardError::Security);
+        }
+        if !top_level {
+            return Err(KeyboardError::InvalidState);
+        }
+        Ok(self.layout.clone())
+    }
+}
+
+/// Whether `code` is a `KeyboardEvent.code` value
+pub fn is_valid_code(code: &str) -> bool {
+    const NAMED: &[&str] = &[
+        "Backquote", "Backslash", "BracketLeft", "BracketRight", "Comma", "Equal", "IntlBackslash",
+        "IntlRo", "IntlYen", "Minus", "Period", "Quote", "Semicolon", "Slash",
+        "AltLeft", "AltRight", "Backspace", "CapsLock", "ContextMenu", "ControlLeft", "ControlRight",
+        "Enter", "MetaLeft", "MetaRight", "ShiftLeft", "ShiftRight", "Space", "Tab",
+        "Delete", "End", "Help", "Home", "Insert", "PageDown", "PageUp",
+        "ArrowDown", "ArrowLeft", "ArrowRight", "ArrowUp", "NumLock",
+        "NumpadAdd", "NumpadDecimal", "NumpadDivide", "NumpadEnter", "NumpadEqual", "NumpadMultiply",
+        "NumpadSubtract", "Escape", "Fn", "PrintScreen", "ScrollLock", "Pause",
+        "BrowserBack", "BrowserForward", "BrowserHome", "BrowserRefresh", "BrowserSearch",
+        "AudioVolumeDown", "AudioVolumeMute", "AudioVolumeUp", "MediaPlayPause", "MediaStop",
+        "MediaTrackNext", "MediaTrackPrevious",
+    ];
+    let single = |rest: &str, valid: fn(&char) -> bool| {
+        let mut chars = rest.chars();
+        matches!((chars.next(), chars.next()), (Some(c), None) if valid(&c))
+    };
+    if let Some(rest) = code.strip_prefix("Key") {
+        return single(rest, char::is_ascii_uppercase);
+    }
+    let digit = code.strip_prefix("Digit").or_else(|| code.strip_prefix("Numpad"));
+    if digit.is_some_and(|rest| single(rest, char::is_ascii_digit)) {
+        return true;
+    }
+    let function = code.strip_prefix('F').and_then(|rest| rest.parse::<u8>().ok().filter(|_| !rest.starts_with('0')));
+    if let Some(n) = function {
+        return (1..=24).contains(&n);
+    }
+    NAMED.contains(&code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock() {
+        let mut keyboard = KeyboardManager::new();
+        assert_eq!(keyboard.lock(&["Escape"], false, true), Err(KeyboardError::InvalidState));
+        assert_eq!(keyboard.lock(&["KeyW", "Esc"], true, true), Err(KeyboardError::InvalidAccess("Esc".into())));
+        keyboard.lock(&["Escape", "MetaLeft", "F11"], true, true).unwrap();
+
+        // Only in fullscreen, and only the locked keys
+        assert!(!keyboard.captures("MetaLeft", false));
+        assert_eq!(keyboard.key_down("MetaLeft", true, 0.0), KeyRouting::Page);
+        assert_eq!(keyboard.key_down("Tab", true, 0.0), KeyRouting::Default);
+
+        // A tap of Escape goes to the page; holding it exits fullscreen
+        assert_eq!(keyboard.key_down("Escape", true, 100.0), KeyRouting::Page);
+        keyboard.key_up("Escape");
+        assert_eq!(keyboard.key_down("Escape", true, 1000.0), KeyRouting::Page);
+        assert_eq!(keyboard.key_down("Escape", true, 2500.0), KeyRouting::Page);
+        assert!(keyboard.escape_held(3000.0));
+        assert!(!keyboard.escape_held(3100.0));
+
+        keyboard.lock(&[], true, true).unwrap();
+        assert!(keyboard.captures("AltLeft", true));
+        keyboard.unlock();
+        assert_eq!(keyboard.key_down("Escape", true, 0.0), KeyRouting::Default);
+    }
+
+    #[test]
+    fn test_layout_map() {
+        let mut keyboard = KeyboardManager::new();
+        assert_eq!(keyboard.get_layout_map(true, false), Err(KeyboardError::Security));
+        assert_eq!(keyboard.get_layout_map(true, true).unwrap().get("KeyW"), Some("w"));
+
+        assert!(keyboard.set_layout("fr"));
+        let map = keyboard.get_layout_map(true, true).unwrap();
+        assert_eq!((map.get("KeyW"), map.get("KeyQ"), map.get("Digit2")), (Some("z"), Some("a"), Some("é")));
+        assert_eq!(KeyboardLayoutMap::builtin("de").unwrap().get("KeyY"), Some("z"));
+
+        assert!(!keyboard.set_layout("dvorak"));
+        keyboard.record_key("KeyE", ".");
+        keyboard.record_key("KeyR", "Enter");
+        let map = keyboard.get_layout_map(true, true).unwrap();
+        assert_eq!((map.get("KeyE"), map.get("KeyR")), (Some("."), Some("r")));
+    }
+}
@@ -16,8 +16,9 @@
 //! # Optional Features (behind feature flags)
 //! - `full` - Enable all optional modules
 //! - `predictive-net` - HTTP/3 and predictive networking
-//! - `device-apis` - Battery, gamepad, sensors
+//! - `device-apis` - Battery, gamepad, sensors, contact picker, WebOTP
 //! - `extensions` - Browser extension support
+//! - `webxr` - WebXR sessions with a desktop emulator device
 
 // ============================================================================
 // CORE MODULES - Essential for browser operation
@@ -29,6 +30,8 @@ pub mod app;
 pub mod page;
 /// Tab management
 pub mod tab;
+/// Session restore (tabs, history, scroll and form state)
+pub mod session;
 /// Navigation controls and URL handling
 pub mod navigation;
 /// Page loader (local files, data URLs)
@@ -37,20 +40,34 @@ pub mod loader;
 pub mod ui;
 /// Page rendering pipeline
 pub mod renderer;
+/// Full-page and text-only zoom
+pub mod zoom;
+/// Installed web apps: launcher shortcuts and icon badges
+pub mod web_apps;
+/// Engine flags set from about:config
+pub mod config;
 /// JavaScript runtime integration
 pub mod js_runtime;
 /// Network requests with HTTP cache
 pub mod network;
+/// Certificate errors the user chose to proceed past
+pub mod cert_overrides;
 /// Developer tools
 pub mod devtools;
 /// Accessibility tree and focus management
 pub mod accessibility;
+/// Reader mode article extraction
+pub mod reader;
 /// Media element handling (video, audio)
 pub mod media;
 /// Canvas 2D rendering
 pub mod canvas;
 /// Security policies (CSP, CORS, sandbox)
 pub mod security;
+/// Reporting API (deprecation, intervention, crash and CSP reports)
+pub mod reporting;
+/// Browser event counts for diagnostics
+pub mod telemetry;
 /// Memory management and pressure handling
 pub mod memory;
 /// Event handling
@@ -59,6 +76,16 @@ pub mod events;
 pub mod storage;
 /// Navigation history
 pub mod history;
+/// Browsing history across sessions, autocomplete and visited links
+pub mod history_store;
+/// Navigation API and back/forward cache
+pub mod navigation_api;
+/// History scroll restoration and scroll anchoring
+pub mod scroll_restoration;
+/// Frame tree and joint session history
+pub mod frame_tree;
+/// Iframes as nested browsing contexts with cross-document messaging
+pub mod frames;
 /// Form data handling
 pub mod forms;
 /// Form autofill with profiles
@@ -75,6 +102,10 @@ pub mod form_history;
 pub mod contenteditable;
 /// Input mode and virtual keyboard
 pub mod input_mode;
+/// IME composition for CJK and emoji input
+pub mod ime;
+/// Spellchecking of editable content
+pub mod spellcheck;
 /// Cookie management
 pub mod cookies;
 /// Advanced networking (WebSocket, XHR, SSE)
@@ -156,23 +187,33 @@ pub mod network_info;
 pub mod geolocation;
 #[cfg(feature = "device-apis")]
 pub mod energy;
+#[cfg(feature = "device-apis")]
+pub mod contacts;
+#[cfg(feature = "device-apis")]
+pub mod web_otp;
+#[cfg(feature = "webxr")]
+pub mod webxr;
 
 // Extension support
 #[cfg(feature = "extensions")]
 pub mod extensions;
 #[cfg(feature = "extensions")]
+pub mod extension_api;
+#[cfg(feature = "extensions")]
+pub mod declarative_net_request;
+#[cfg(feature = "extensions")]
 pub mod plugins;
 
 // Additional web APIs (included with 'full' feature)
 #[cfg(feature = "full")]
 pub mod notifications;
 #[cfg(feature = "full")]
-pub mod dragdrop;
-#[cfg(feature = "full")]
 pub mod permissions;
 #[cfg(feature = "full")]
 pub mod fullscreen;
 #[cfg(feature = "full")]
+pub mod keyboard_lock;
+#[cfg(feature = "full")]
 pub mod builtins;
 #[cfg(feature = "full")]
 pub mod touch;
@@ -198,11 +239,17 @@ pub mod share;
 pub mod broadcast;
 #[cfg(feature = "full")]
 pub mod page_visibility;
-#[cfg(feature = "full")]
+/// Pointer events
 pub mod pointer;
+/// Pointer Lock API
+pub mod pointer_lock;
+/// Drag and drop, including files from and to the OS
+pub mod dragdrop;
 #[cfg(feature = "full")]
 pub mod mutation_observer;
 #[cfg(feature = "full")]
+pub mod emulation;
+#[cfg(feature = "full")]
 pub mod find;
 #[cfg(feature = "full")]
 pub mod downloads;
@@ -214,6 +261,10 @@ pub mod bookmarks;
 pub mod print;
 #[cfg(feature = "full")]
 pub mod passwords;
+#[cfg(feature = "full")]
+pub mod http_auth;
+#[cfg(feature = "full")]
+pub mod payment;
 
 // ============================================================================
 // PUBLIC EXPORTS - Core types available by default
@@ -222,21 +273,32 @@ pub mod passwords;
 pub use app::Browser;
 pub use page::Page;
 pub use tab::Tab;
-pub use renderer::{PageRenderer, RenderedPage};
+pub use renderer::{PageRenderer, RenderedPage, BlockedFrame, FrameContent, PrintedPage, TextRun};
 pub use js_runtime::PageJsRuntime;
-pub use network::NetworkManager;
+pub use network::{NetworkManager, CachedResource};
+pub use cert_overrides::CertificateOverrides;
+pub use web_apps::{WebApps, InstalledApp, AppShortcut, AppLauncher, DesktopEntries, WebAppError};
+pub use config::{ConfigStore, ConfigFlag, FlagValue, ConfigError};
 pub use devtools::DevTools;
 pub use accessibility::AccessibilityManager;
+pub use reader::Article as ReaderArticle;
 pub use media::MediaManager;
 pub use canvas::CanvasManager;
 pub use advanced_net::AdvancedNetworking;
 pub use security::SecurityManager;
-pub use memory::MemoryIntegration;
+pub use reporting::{ReportingManager, ReportingObserver, Report, ReportBody, ReportType};
+pub use telemetry::{Telemetry, TelemetryEvent};
+pub use memory::{MemoryIntegration, HibernationCandidate, TabMemory};
 pub use events::EventManager;
 pub use storage::StorageManager;
 pub use workers::WorkerIntegration;
 pub use webapi::WebApiManager;
 pub use history::NavigationIntegration;
+pub use history_store::{HistoryStore, VisitedUrl, Suggestion, VisitedLinks};
+pub use navigation_api::{Navigation, NavigateEvent, NavigationHistoryEntry, BackForwardCache};
+pub use scroll_restoration::{ScrollRestoration, ScrollRestorer, ScrollSnapshot, ScrollAnchor};
+pub use frame_tree::{FrameEntry, FrameId, FrameTree};
+pub use frames::{Frames, ChildFrame, FrameRequest};
 pub use profiling::PerformanceProfiler;
 pub use optimization::OptimizationManager;
 pub use compat::CompatibilityManager;
@@ -258,15 +320,17 @@ pub use cache_manager::{CacheManager, CacheManagerStats, CacheStats, LruCache, D
 
 // Form and cookie exports
 pub use forms::{FormData, FormCollector, FormMethod};
-pub use autofill::{AutofillManager, AutofillProfile, AutofillFieldType};
+pub use autofill::{AutofillManager, AutofillProfile, AutofillFieldType, AutofillKey, AutofillPreview, FormField};
 pub use datalist::{Datalist, DatalistRegistry, DatalistOption};
 pub use constraint_validation::{ValidityState as ConstraintValidityState, FormValidator, ValidatableElement};
 pub use file_upload::{FileUploadManager, FileList, FileEntry, AcceptFilter};
 pub use form_history::{FormHistoryManager, FieldKey};
 pub use contenteditable::{ContentEditor, EditCommand, EditSelection};
 pub use input_mode::{InputMode, EnterKeyHint, VirtualKeyboardManager};
+pub use ime::{ImeManager, ImeInput, ImeContext, ImeUpdate, Composition, UnderlineStyle};
+pub use spellcheck::{SpellChecker, Dictionary, Misspelling, SpellcheckMenu, SpellcheckAction};
 pub use cookies::{Cookie, CookieJar};
-pub use service_worker::{ServiceWorkerManager, CacheStorage};
+pub use service_worker::{ServiceWorkerManager, CacheStorage, NavigationPreloadState, PreloadResponse};
 pub use indexeddb::{IDBFactory, IDBDatabase};
 
 // ============================================================================
@@ -281,13 +345,17 @@ pub use predictive::{PredictiveNetwork, ResourceHint};
 #[cfg(feature = "device-apis")]
 pub use battery::BatteryManager;
 #[cfg(feature = "device-apis")]
-pub use gamepad::{GamepadManager, Gamepad, GamepadButton};
+pub use gamepad::{GamepadManager, Gamepad, GamepadButton, GamepadHapticEffectType, GamepadEffectParameters, HapticsBackend};
 #[cfg(feature = "device-apis")]
-pub use sensors::{SensorsManager, Accelerometer, Gyroscope, DeviceOrientation};
+pub use sensors::{SensorsManager, SensorProvider, Accelerometer, Gyroscope, DeviceOrientation};
 #[cfg(feature = "device-apis")]
 pub use network_info::{NetworkInfoManager, NetworkInformation, ConnectionType};
 #[cfg(feature = "device-apis")]
-pub use geolocation::{GeolocationManager, Position, Coordinates};
+pub use geolocation::{GeolocationManager, PositionProvider, Position, Coordinates};
+#[cfg(feature = "device-apis")]
+pub use contacts::{ContactsManager, ContactsProvider, ContactProperty, ContactInfo};
+#[cfg(feature = "device-apis")]
+pub use web_otp::{OtpManager, SmsRetriever};
 #[cfg(feature = "device-apis")]
 pub use energy::{
     EnergyManager, EnergyStats,
@@ -303,7 +371,9 @@ pub use energy::{
 };
 
 #[cfg(feature = "extensions")]
-pub use extensions::{ExtensionManager, Extension, ExtensionManifest};
+pub use extensions::{ExtensionManager, Extension, ExtensionManifest, MatchPattern};
+#[cfg(feature = "extensions")]
+pub use extension_api::{ExtensionRuntime, ExtensionContext, ExtensionTabs, TabInfo, StorageArea};
 #[cfg(feature = "extensions")]
 pub use plugins::PluginManager;
 
@@ -320,21 +390,23 @@ pub use print::{PrintManager, PrintSettings};
 #[cfg(feature = "full")]
 pub use passwords::PasswordManager;
 #[cfg(feature = "full")]
-pub use notifications::{NotificationManager, Notification, NotificationPermission};
+pub use http_auth::{HttpAuthPrompter, LoginInput};
 #[cfg(feature = "full")]
-pub use dragdrop::{DragDropManager, DataTransfer, DragEvent};
+pub use notifications::{NotificationManager, Notification, NotificationPermission};
 #[cfg(feature = "full")]
 pub use permissions::{PermissionsManager, PermissionName, PermissionState};
 #[cfg(feature = "full")]
 pub use fullscreen::{FullscreenManager, WakeLockManager};
 #[cfg(feature = "full")]
+pub use keyboard_lock::{KeyboardManager, KeyboardLayoutMap, KeyRouting};
+#[cfg(feature = "full")]
 pub use builtins::{BuiltinsManager, AsyncContext};
 #[cfg(feature = "full")]
 pub use touch::{TouchManager, Touch, TouchEvent, Gesture};
 #[cfg(feature = "full")]
 pub use validation::{InputValidator, ValidityState, ValidationConstraints};
 #[cfg(feature = "full")]
-pub use selection::{SelectionManager, Selection, TextRange};
+pub use selection::{SelectionManager, Selection, SelectionGranularity, TextRange};
 #[cfg(feature = "full")]
 pub use scroll::{ScrollManager, ScrollBehavior, ScrollPosition};
 #[cfg(feature = "full")]
@@ -350,10 +422,15 @@ pub use dialog::{DialogManager, Dialog, BuiltinDialogs};
 #[cfg(feature = "full")]
 pub use share::{ShareManager, ShareData};
 #[cfg(feature = "full")]
+pub use payment::{PaymentManager, PaymentHandler, PaymentRequest, PaymentResponse};
+#[cfg(feature = "full")]
 pub use broadcast::{BroadcastChannelManager, BroadcastChannel, BroadcastMessage};
 #[cfg(feature = "full")]
 pub use page_visibility::{PageVisibilityManager, DocumentVisibility};
-#[cfg(feature = "full")]
 pub use pointer::{PointerManager, PointerEvent, PointerType};
+pub use pointer_lock::{PointerLockManager, PointerLockOptions, PointerLockError, CursorCapture};
+pub use dragdrop::{DragDropManager, DataTransfer, DragEvent, PageDragEvent, OsDragSource};
 #[cfg(feature = "full")]
 pub use mutation_observer::{MutationObserverManager, MutationObserver, MutationRecord};
+#[cfg(feature = "full")]
+pub use emulation::{EmulationManager, SensorOverrides};
@@ -1,14 +1,98 @@
 //! Page Loader
 //!
-//! Fetches and processes web pages.
+//! Fetches and processes web pages, and generates the `about:` pages,
+//! certificate error interstitials included.
+//!
+//! The internal pages (about:memory, about:cache and about:config) are
+//! generated from a snapshot of browser state and go through the same
+//! pipeline as any other page. Their buttons are links to the page with
+//! an action in the query, like `about:cache?evict=<url>`.
+//!
+//! Navigations into a service worker's scope go to the worker first. No
+//! worker script runs, so the worker answers as a cache-first handler
+//! would: from its caches, else with the navigation preload response when
+//! the registration has preload turned on. The preload request goes out
+//! through the network manager like any other navigation.
 
+use crate::config::{ConfigFlag, FlagValue};
+use crate::memory::TabMemory;
+use crate::network::{CachedResource, FetchResult, NetworkError, NetworkManager};
 use crate::page::Page;
+use crate::service_worker::{CachedResponse, ServiceWorkerManager};
+use fos_engine::MemoryStats;
+use fos_engine::url::{Query, percent_encode};
+use fos_net::TlsError;
+use std::collections::HashMap;
 use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+/// Link on a certificate error page that proceeds to the site anyway
+pub const CERT_ERROR_PROCEED: &str = "about:certerror?proceed";
+
+/// Browser state the internal pages show, gathered as one loads
+#[derive(Debug, Default)]
+pub struct InternalState {
+    /// Memory across every window
+    pub memory: MemoryStats,
+    /// Tabs of the window loading the page
+    pub tabs: Vec<TabMemory>,
+    pub cache: Vec<CachedResource>,
+    pub config: Vec<ConfigFlag>,
+}
+
+/// Change asked for by a button on an internal page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InternalAction {
+    /// Drop a URL from the HTTP cache
+    EvictCache(String),
+    /// Set a config flag from its text form
+    SetConfig(String, String),
+    /// Put a config flag back to its default
+    ResetConfig(String),
+}
+
+impl InternalAction {
+    /// Action in an internal page URL, if it has one
+    pub fn parse(url: &str) -> Option<Self> {
+        let (page, query) = url.split_once('?')?;
+        let query = Query::parse(query.split('#').next().unwrap_or(""));
+        match page {
+            "about:cache" => query.get("evict").map(|url| Self::EvictCache(url.to_string())),
+            "about:config" => {
+                if let Some(name) = query.get("reset") {
+                    return Some(Self::ResetConfig(name.to_string()));
+                }
+                let name = query.get("set")?;
+                Some(Self::SetConfig(name.to_string(), query.get("value").unwrap_or("").to_string()))
+            }
+            _ => None,
+        }
+    }
+    
+    /// URL of the button that asks for this action
+    pub fn url(&self) -> String {
+        match self {
+            Self::EvictCache(url) => format!("about:cache?evict={}", percent_encode(url)),
+            Self::SetConfig(name, value) => format!("about:config?set={}&value={}", percent_encode(name), percent_encode(value)),
+            Self::ResetConfig(name) => format!("about:config?reset={}", percent_encode(name)),
+        }
+    }
+    
+    /// Internal page to show once the action is done
+    pub fn page(&self) -> &'static str {
+        match self {
+            Self::EvictCache(_) => "about:cache",
+            Self::SetConfig(..) | Self::ResetConfig(_) => "about:config",
+        }
+    }
+}
 
 /// Page loader
 pub struct Loader {
     /// User agent string
     user_agent: String,
+    /// Service workers that answer navigations into their scopes
+    service_workers: Arc<Mutex<ServiceWorkerManager>>,
 }
 
 impl Loader {
@@ -18,9 +102,15 @@ impl Loader {
             user_agent: format!(
                 "fOS-Browser/0.1 (compatible; fOS Engine; +https://github.com/fosproject)"
             ),
+            service_workers: Arc::new(Mutex::new(ServiceWorkerManager::new())),
         }
     }
     
+    /// Service workers, shared with the pages whose registrations they are
+    pub fn service_workers(&self) -> &Arc<Mutex<ServiceWorkerManager>> {
+        &self.service_workers
+    }
+    
     /// Load a page from URL (blocking)
     pub fn load_sync(&self, url: &str) -> Result<Page, Box<dyn Error>> {
         // Handle about: URLs
@@ -52,9 +142,58 @@ impl Loader {
         Ok(html)
     }
     
+    /// Navigate to `url` through the service worker controlling it: its
+    /// cached response, else the preload response fetched through
+    /// `network`. `None` if no worker answers and the navigation goes to
+    /// the network as usual.
+    pub fn fetch_through_worker(&self, url: &str, network: &mut NetworkManager) -> Option<Result<FetchResult, NetworkError>> {
+        // The workers are only held while the navigation is handed over,
+        // not across the network round trip
+        let navigation = self.service_workers.lock().unwrap().intercept_navigation(url, HashMap::new())?;
+        if let Some(cached) = navigation.cached {
+            return Some(worker_response(cached));
+        }
+        
+        let request = navigation.preload_request.as_ref()?;
+        let headers: Vec<(String, String)> = request.headers.iter()
+            .map(|(n, v)| (n.clone(), v.clone()))
+            .collect();
+        let result = network.fetch_navigation(url, &headers);
+        navigation.settle_preload(match &result {
+            Ok(result) => Ok(CachedResponse::new(result.status, result.body.clone())
+                .with_headers(result.headers.iter().cloned().collect())),
+            Err(e) => Err(e.to_string()),
+        });
+        Some(result)
+    }
+    
     /// Load an about: page
     fn load_about_page(&self, url: &str) -> Page {
-        let html = match url {
+        Page::from_html(url, self.about_html(url))
+    }
+    
+    /// HTML of an about: page, generating the internal pages from `state`
+    pub fn internal_page_html(&self, url: &str, state: &InternalState) -> String {
+        let body = match url.split(['?', '#']).next().unwrap_or(url) {
+            "about:memory" => memory_page(state),
+            "about:cache" => cache_page(&state.cache),
+            "about:config" => config_page(&state.config),
+            _ => return self.about_html(url),
+        };
+        format!(r#"
+            <!DOCTYPE html>
+            <html>
+            <head><title>{}</title></head>
+            <body style="background: #0d0d0d; color: #e0e0e0; font-family: sans-serif; padding: 20px;">
+                <h1>{}</h1>
+                {}
+            </body>
+            </html>
+        "#, body.title, body.title, body.content)
+    }
+    
+    fn about_html(&self, url: &str) -> String {
+        match url {
             "about:blank" => r#"
                 <!DOCTYPE html>
                 <html>
@@ -86,13 +225,180 @@ impl Loader {
                     <p>The page <code>{}</code> was not found.</p>
                 </body>
                 </html>
-            "#, url),
+            "#, escape(url)),
+        }
+    }
+    
+    /// Interstitial shown instead of `url` when its certificate failed
+    /// validation. Only errors that can be overridden, on hosts without
+    /// HSTS, offer to proceed.
+    pub fn certificate_error_page(&self, url: &str, error: &TlsError, hsts: bool) -> Page {
+        let host = fos_engine::url::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_else(|| url.to_string());
+        let host = escape(&host);
+        let explanation = match error {
+            TlsError::CertificateExpired => format!("The certificate of {} has expired, or your computer's clock is wrong.", host),
+            TlsError::CertificateNotYetValid => format!("The certificate of {} is not valid yet, or your computer's clock is wrong.", host),
+            TlsError::UnknownIssuer => format!("The certificate of {} is not issued by an authority this browser trusts. It may be self-signed.", host),
+            TlsError::NameMismatch(_) => format!("The certificate presented is not for {}. Someone may be impersonating the site.", host),
+            TlsError::CertificateRevoked => format!("The certificate of {} has been revoked by its issuer.", host),
+            TlsError::InvalidCertificate(_) | TlsError::Handshake(_) => format!("{} sent a certificate that could not be validated.", host),
+        };
+        let advanced = if hsts {
+            format!("<p>{} uses HTTP Strict Transport Security, so it cannot be visited while its certificate is invalid.</p>", host)
+        } else if error.is_overridable() {
+            format!(r#"<p>If you understand the risk, you can <a href="{}">proceed to {} (unsafe)</a>.</p>"#, CERT_ERROR_PROCEED, host)
+        } else {
+            String::new()
         };
+        let html = format!(r#"
+            <!DOCTYPE html>
+            <html>
+            <head><title>Connection not private</title></head>
+            <body style="background: #1a1a1a; color: #e0e0e0; font-family: sans-serif; padding: 20px;">
+                <h1>Your connection is not private</h1>
+                <p>{}</p>
+                <p>Attackers might be trying to steal your information from {}, such as passwords, messages or card numbers.</p>
+                <p><code>{}</code></p>
+                <p><a href="about:blank">Back to safety</a></p>
+                <h2>Advanced</h2>
+                <p>Details: {}</p>
+                {}
+            </body>
+            </html>
+        "#, explanation, host, error.code(), escape(&error.to_string()), advanced);
         
-        Page::from_html(url, html)
+        Page::from_html("about:certerror", html)
+    }
+}
+
+/// Title and body of a generated internal page
+struct InternalPage {
+    title: &'static str,
+    content: String,
+}
+
+fn memory_page(state: &InternalState) -> InternalPage {
+    let memory = &state.memory;
+    let mut content = format!(
+        "<p>Heap: {} of {}, {} DOM nodes, {} layout objects</p>\n",
+        format_bytes(memory.heap_used as u64),
+        format_bytes(memory.heap_total as u64),
+        memory.dom_nodes,
+        memory.layout_objects,
+    );
+    content.push_str("<table>\n<tr><th>Tab</th><th>Memory</th><th>DOM nodes</th><th>Hot</th><th>Warm</th><th>Cold</th></tr>\n");
+    for tab in &state.tabs {
+        let tiers = &tab.tiers;
+        content.push_str(&format!(
+            "<tr><td>{}{}<br><small>{}</small></td><td>{}</td><td>{}</td><td>{} ({})</td><td>{} ({})</td><td>{}</td></tr>\n",
+            escape(&tab.title),
+            if tab.hibernated { " (hibernated)" } else { "" },
+            escape(&tab.url),
+            format_bytes(tab.stats.heap_used as u64),
+            tab.stats.dom_nodes,
+            tiers.hot_count,
+            format_bytes(tiers.hot_bytes as u64),
+            tiers.warm_count,
+            format_bytes(tiers.warm_bytes as u64),
+            tiers.cold_count,
+        ));
+    }
+    content.push_str("</table>");
+    InternalPage { title: "Memory", content }
+}
+
+fn cache_page(cache: &[CachedResource]) -> InternalPage {
+    let total: usize = cache.iter().map(|resource| resource.size).sum();
+    let mut content = format!("<p>{} responses, {}</p>\n", cache.len(), format_bytes(total as u64));
+    content.push_str("<table>\n<tr><th>URL</th><th>Type</th><th>Size</th><th>Fresh for</th><th>Hits</th><th></th></tr>\n");
+    for resource in cache {
+        let evict = InternalAction::EvictCache(resource.url.clone()).url();
+        content.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}s</td><td>{}</td><td><a href=\"{}\">Evict</a></td></tr>\n",
+            escape(&resource.url),
+            escape(&resource.content_type),
+            format_bytes(resource.size as u64),
+            resource.ttl.as_secs(),
+            resource.hits,
+            escape(&evict),
+        ));
+    }
+    content.push_str("</table>");
+    InternalPage { title: "Cache", content }
+}
+
+fn config_page(flags: &[ConfigFlag]) -> InternalPage {
+    let mut content = String::from(
+        "<p>Changes apply from the next page load. Set other values by typing \
+        <code>about:config?set=&lt;flag&gt;&amp;value=&lt;value&gt;</code> in the address bar.</p>\n",
+    );
+    content.push_str("<table>\n<tr><th>Flag</th><th>Value</th><th></th></tr>\n");
+    for flag in flags {
+        // Booleans flip in place; other values are typed in
+        let mut buttons = String::new();
+        if let FlagValue::Bool(value) = flag.value {
+            let toggle = InternalAction::SetConfig(flag.name.to_string(), (!value).to_string()).url();
+            buttons.push_str(&format!(r#"<a href="{}">Toggle</a> "#, escape(&toggle)));
+        }
+        if flag.modified {
+            let reset = InternalAction::ResetConfig(flag.name.to_string()).url();
+            buttons.push_str(&format!(r#"<a href="{}">Reset</a>"#, escape(&reset)));
+        }
+        let name = if flag.modified { format!("<b>{}</b>", flag.name) } else { flag.name.to_string() };
+        content.push_str(&format!(
+            "<tr><td>{}<br><small>{}</small></td><td><code>{}</code></td><td>{}</td></tr>\n",
+            name,
+            escape(flag.description),
+            escape(&flag.value.to_string()),
+            buttons,
+        ));
+    }
+    content.push_str("</table>");
+    InternalPage { title: "Config", content }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    
+    if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.1} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} B", bytes)
     }
 }
 
+/// A response from a service worker's caches, as a fetch result
+fn worker_response(response: CachedResponse) -> Result<FetchResult, NetworkError> {
+    if !(200..300).contains(&response.status) {
+        return Err(NetworkError::HttpError(response.status));
+    }
+    let mut headers: Vec<(String, String)> = response.headers.into_iter().collect();
+    headers.sort();
+    let content_type = headers.iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case("content-type"))
+        .map(|(_, v)| v.clone())
+        .unwrap_or_else(|| "text/html".to_string());
+    Ok(FetchResult {
+        body: response.body,
+        content_type,
+        from_cache: true,
+        status: response.status,
+        headers,
+        timings: None,
+        overridden: false,
+    })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
 impl Default for Loader {
     fn default() -> Self {
         Self::new()
@@ -118,4 +424,112 @@ mod tests {
         
         assert_eq!(page.title, Some("fOS Browser".to_string()));
     }
+    
+    #[test]
+    fn test_internal_pages() {
+        let loader = Loader::new();
+        let mut state = InternalState::default();
+        state.cache.push(CachedResource {
+            url: "https://example.com/a b?x=1&y=<2>".to_string(),
+            content_type: "text/html".to_string(),
+            size: 2048,
+            ttl: std::time::Duration::from_secs(60),
+            hits: 3,
+        });
+        state.config = crate::config::ConfigStore::new().flags();
+        
+        let page = Page::from_html("about:cache", loader.internal_page_html("about:cache?evict=x", &state));
+        assert_eq!(page.title, Some("Cache".to_string()));
+        assert!(page.html.contains("2.0 KB"));
+        let evict = InternalAction::EvictCache(state.cache[0].url.clone());
+        assert!(page.html.contains(&escape(&evict.url())));
+        assert_eq!(InternalAction::parse(&evict.url()), Some(evict));
+        
+        let page = Page::from_html("about:config", loader.internal_page_html("about:config", &state));
+        let toggle = InternalAction::SetConfig("enable_javascript".to_string(), "false".to_string());
+        assert!(page.html.contains(&escape(&toggle.url())));
+        assert_eq!(InternalAction::parse("about:config?set=enable_javascript&value=false"), Some(toggle));
+        assert_eq!(InternalAction::parse("about:config?reset=user_agent"), Some(InternalAction::ResetConfig("user_agent".to_string())));
+        assert_eq!(InternalAction::parse("about:memory?evict=x"), None);
+        
+        let page = Page::from_html("about:memory", loader.internal_page_html("about:memory", &state));
+        assert_eq!(page.title, Some("Memory".to_string()));
+        assert!(loader.internal_page_html("about:blank", &state).contains("<title>New Tab</title>"));
+    }
+    
+    #[test]
+    fn test_navigation_preload() {
+        use crate::service_worker::registration_api;
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let scope = format!("http://127.0.0.1:{}/", listener.local_addr().unwrap().port());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut head = Vec::new();
+            let mut byte = [0u8];
+            while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap() == 1 {
+                head.push(byte[0]);
+            }
+            let head = String::from_utf8(head).unwrap().to_ascii_lowercase();
+            let preloaded = head.contains("service-worker-navigation-preload: v2") && head.contains("user-agent: test-agent");
+            let body = if preloaded { "<title>Preloaded</title>" } else { "<title>Plain</title>" };
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body).unwrap();
+        });
+        
+        let loader = Loader::new();
+        let mut network = NetworkManager::new();
+        network.set_user_agent("test-agent");
+        {
+            let mut workers = loader.service_workers().lock().unwrap();
+            let id = workers.register(&format!("{}sw.js", scope), None).unwrap();
+            workers.complete_install(id);
+            workers.complete_activate(id);
+        }
+        
+        // Controlled, but without preload the network answers as usual
+        let url = format!("{}index.html", scope);
+        assert!(loader.fetch_through_worker(&url, &mut network).is_none());
+        
+        // A page in the scope turns preload on through its registration
+        let mut page = Page::from_html(&scope, "<html></html>".to_string());
+        page.initialize_javascript().unwrap();
+        let runtime = page.js_runtime.as_ref().unwrap();
+        runtime.install_host_api(&registration_api(loader.service_workers(), &scope));
+        runtime.eval("registration.navigationPreload.enable(); registration.navigationPreload.setHeaderValue('v2');").unwrap();
+        assert!(runtime.eval("registration.navigationPreload.setHeaderValue('a\\nb');").is_err());
+        
+        // The preload goes out through the network manager with the header
+        let result = loader.fetch_through_worker(&url, &mut network).unwrap().unwrap();
+        assert_eq!(result.body, b"<title>Preloaded</title>");
+        assert!(!result.from_cache);
+        server.join().unwrap();
+        
+        // What the worker cached answers without a request
+        let cached = format!("{}offline.html", scope);
+        loader.service_workers().lock().unwrap().cache_storage_mut().open("v1")
+            .put(&cached, CachedResponse::new(200, b"<title>Offline</title>".to_vec()));
+        let result = loader.fetch_through_worker(&cached, &mut network).unwrap().unwrap();
+        assert!(result.from_cache);
+        assert_eq!(result.body, b"<title>Offline</title>");
+        
+        // Outside any scope the worker is not involved
+        assert!(loader.fetch_through_worker("http://example.invalid/", &mut network).is_none());
+    }
+    
+    #[test]
+    fn test_certificate_error_page() {
+        let loader = Loader::new();
+        let page = loader.certificate_error_page("https://self-signed.example/", &TlsError::UnknownIssuer, false);
+        assert_eq!(page.title, Some("Connection not private".to_string()));
+        assert!(page.html.contains("ERR_CERT_AUTHORITY_INVALID"));
+        assert!(page.html.contains(CERT_ERROR_PROCEED));
+        
+        // No way through for HSTS hosts or revoked certificates
+        let page = loader.certificate_error_page("https://self-signed.example/", &TlsError::UnknownIssuer, true);
+        assert!(!page.html.contains(CERT_ERROR_PROCEED));
+        let page = loader.certificate_error_page("https://revoked.example/", &TlsError::CertificateRevoked, false);
+        assert!(!page.html.contains(CERT_ERROR_PROCEED));
+    }
 }
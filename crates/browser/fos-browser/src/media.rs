@@ -3,10 +3,30 @@
 //! Integrates fos-media for video and audio element support.
 
 use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use fos_dom::{Document, DomTree, NodeId};
+use fos_engine::AudioThread;
 use fos_media::{
     HTMLVideoElement, HTMLAudioElement,
+    AudioEngine, AudioDevice, OutputConfig, OutputError, MixerInputId, SharedSampleQueue,
+    AudioMixer, AudioGroup, AudioCaptureStream, AudioRenderSource,
+    ScreenCapture, DisplayMediaRequest, DisplayMediaStreamOptions, MediaStream,
+    CaptureSource, CapturedFrame, ScreenCaptureError,
+    UserMedia, UserMediaError, MediaStreamConstraints, MediaTrackConstraints, MediaTrackSettings,
+    MediaDeviceInfo, MediaDeviceKind,
+    MediaSession, MediaSessionController, MediaSessionActionDetails,
+    TextTrack, TextTrackKind, TextTrackMode, TextTrackEvent, TextMeasure, VttError,
+    ProgressiveLoader, LoadEvent,
+    MediaCapabilities, MediaCapabilitiesInfo, MediaCapabilitiesError,
+    MediaDecodingConfiguration, MediaEncodingConfiguration,
 };
+use fos_render::{Canvas, Color, TextRenderer};
+use fos_text::FontId;
+#[cfg(feature = "full")]
+use crate::permissions::{PermissionDescriptor, PermissionName, PermissionState, PermissionsManager};
 
 /// Media manager for the browser
 pub struct MediaManager {
@@ -16,6 +36,58 @@ pub struct MediaManager {
     audios: HashMap<u64, AudioInstance>,
     /// Next media ID
     next_id: u64,
+    /// Audio output (mixer + device), rendered on the audio thread
+    audio_engine: Arc<Mutex<AudioEngine>>,
+    /// Thread running the render pump while output is started
+    audio_thread: Option<AudioThread>,
+    /// Render loop keep-alive flag
+    audio_running: Arc<AtomicBool>,
+    /// Per-tab audio groups on the output mixer
+    tab_audio: HashMap<u64, TabAudio>,
+    /// Tab that newly played media is routed to
+    current_tab: Option<u64>,
+    /// Running `getDisplayMedia` captures
+    screen_capture: ScreenCapture,
+    /// Requesting tab of each display capture stream
+    display_streams: HashMap<String, u64>,
+    /// Running `getUserMedia` camera and microphone captures
+    user_media: UserMedia,
+    /// Requesting tab of each camera/microphone stream
+    user_streams: HashMap<String, u64>,
+    /// `navigator.mediaSession` of each tab
+    media_sessions: HashMap<u64, MediaSession>,
+    /// OS now-playing surface and media keys
+    media_session_controller: MediaSessionController,
+    /// Most recently played element, controlled by media keys
+    active_media: Option<ActiveMedia>,
+    /// `navigator.mediaCapabilities`, probed once for hardware decoders
+    media_capabilities: MediaCapabilities,
+}
+
+/// Element the OS media session controls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ActiveMedia {
+    Video { tab_id: Option<u64>, id: u64 },
+    Audio { tab_id: Option<u64>, id: u64 },
+}
+
+impl ActiveMedia {
+    fn tab_id(&self) -> Option<u64> {
+        match *self {
+            Self::Video { tab_id, .. } | Self::Audio { tab_id, .. } => tab_id,
+        }
+    }
+}
+
+/// Audio routing state of one tab
+#[derive(Debug)]
+pub struct TabAudio {
+    /// Input of the tab's group on the master mixer
+    input: MixerInputId,
+    /// Sub-mixer for all of the tab's media
+    group: AudioGroup,
+    /// Tab is muted by the user
+    pub muted: bool,
 }
 
 /// Video element instance
@@ -35,6 +107,12 @@ pub struct AudioInstance {
     pub element: HTMLAudioElement,
     pub src: String,
     pub loaded: bool,
+    /// Mixer input receiving this element's decoded audio
+    pub output: Option<(MixerInputId, SharedSampleQueue)>,
+    /// Tab group the mixer input belongs to (`None` = master mixer)
+    pub tab_id: Option<u64>,
+    /// Ranged download of `src` while it plays
+    pub loader: Option<ProgressiveLoader>,
 }
 
 /// Media element bounds for rendering
@@ -53,12 +131,383 @@ impl MediaManager {
             videos: HashMap::new(),
             audios: HashMap::new(),
             next_id: 1,
+            audio_engine: Arc::new(Mutex::new(AudioEngine::default())),
+            audio_thread: None,
+            audio_running: Arc::new(AtomicBool::new(false)),
+            tab_audio: HashMap::new(),
+            current_tab: None,
+            screen_capture: ScreenCapture::new(),
+            display_streams: HashMap::new(),
+            user_media: UserMedia::new(),
+            user_streams: HashMap::new(),
+            media_sessions: HashMap::new(),
+            media_session_controller: MediaSessionController::new(),
+            active_media: None,
+            media_capabilities: MediaCapabilities::new(),
+        }
+    }
+    
+    /// Route media of subsequently loaded documents to this tab's audio group
+    pub fn set_current_tab(&mut self, tab_id: u64) {
+        self.current_tab = Some(tab_id);
+    }
+    
+    /// Audio group of a tab, created on first use
+    fn tab_group(&mut self, tab_id: u64) -> AudioGroup {
+        if let Some(tab) = self.tab_audio.get(&tab_id) {
+            return Arc::clone(&tab.group);
+        }
+        
+        let group: AudioGroup = Default::default();
+        let mixer = self.audio_engine.lock().unwrap().mixer();
+        let input = mixer.lock().unwrap().add_input(Box::new(Arc::clone(&group)));
+        self.tab_audio.insert(tab_id, TabAudio { input, group: Arc::clone(&group), muted: false });
+        group
+    }
+    
+    /// Mute or unmute all audio of a tab. Captures of the tab keep receiving audio.
+    pub fn set_tab_muted(&mut self, tab_id: u64, muted: bool) {
+        self.tab_group(tab_id);
+        if let Some(tab) = self.tab_audio.get_mut(&tab_id) {
+            tab.muted = muted;
+            let mixer = self.audio_engine.lock().unwrap().mixer();
+            mixer.lock().unwrap().set_muted(tab.input, muted);
+        }
+    }
+    
+    /// Check if a tab is muted
+    pub fn is_tab_muted(&self, tab_id: u64) -> bool {
+        self.tab_audio.get(&tab_id).map(|t| t.muted).unwrap_or(false)
+    }
+    
+    /// Whether a tab produced sound recently (drives the tab strip indicator)
+    pub fn is_tab_audible(&self, tab_id: u64) -> bool {
+        self.tab_audio.get(&tab_id)
+            .map(|t| t.group.lock().map(|g| g.is_audible()).unwrap_or(false))
+            .unwrap_or(false)
+    }
+    
+    /// Tabs that are currently audible
+    pub fn audible_tabs(&self) -> Vec<u64> {
+        self.tab_audio.keys().copied().filter(|&id| self.is_tab_audible(id)).collect()
+    }
+    
+    /// Capture the mixed audio output of a tab (tabCapture, screen recording)
+    pub fn capture_tab_audio(&mut self, tab_id: u64) -> AudioCaptureStream {
+        self.tab_group(tab_id).lock().unwrap().capture()
+    }
+    
+    /// Drop a closed tab's audio group
+    pub fn remove_tab(&mut self, tab_id: u64) {
+        if let Some(tab) = self.tab_audio.remove(&tab_id) {
+            let mixer = self.audio_engine.lock().unwrap().mixer();
+            mixer.lock().unwrap().remove_input(tab.input);
+        }
+        for audio in self.audios.values_mut() {
+            if audio.tab_id == Some(tab_id) {
+                audio.output = None;
+                audio.tab_id = None;
+            }
+        }
+        if self.current_tab == Some(tab_id) {
+            self.current_tab = None;
+        }
+        
+        // Captures of the tab end, as do captures it started
+        self.screen_capture.stop_tab_source(tab_id);
+        let started: Vec<String> = self.display_streams.iter()
+            .filter(|(_, requester)| **requester == tab_id)
+            .map(|(stream, _)| stream.clone())
+            .collect();
+        for stream in started {
+            self.stop_display_capture(&stream);
+        }
+        let started: Vec<String> = self.user_streams.iter()
+            .filter(|(_, requester)| **requester == tab_id)
+            .map(|(stream, _)| stream.clone())
+            .collect();
+        for stream in started {
+            self.stop_user_media(&stream);
+        }
+        
+        self.media_sessions.remove(&tab_id);
+        if self.active_media.is_some_and(|m| m.tab_id() == Some(tab_id)) {
+            self.active_media = None;
+            self.media_session_controller.clear();
+        }
+    }
+    
+    /// Drop audio groups of tabs that no longer exist
+    pub fn retain_tabs(&mut self, open_tabs: &[u64]) {
+        let closed: Vec<u64> = self.tab_audio.keys()
+            .copied()
+            .filter(|id| !open_tabs.contains(id))
+            .collect();
+        for id in closed {
+            self.remove_tab(id);
+        }
+    }
+    
+    /// Sources for the `getDisplayMedia` picker; `tabs` are (tab id, title) of
+    /// the open tabs
+    pub fn request_display_media(
+        &self,
+        options: DisplayMediaStreamOptions,
+        tabs: &[(u64, String)],
+        viewport: (u32, u32),
+    ) -> Result<DisplayMediaRequest, ScreenCaptureError> {
+        let tabs = tabs.iter()
+            .map(|(id, title)| CaptureSource::tab(*id, title, viewport.0, viewport.1))
+            .collect();
+        self.screen_capture.request(options, tabs)
+    }
+    
+    /// Start the display capture picked by the user on behalf of `tab_id`
+    pub fn start_display_capture(
+        &mut self,
+        tab_id: u64,
+        request: &DisplayMediaRequest,
+        source_id: Option<&str>,
+    ) -> Result<MediaStream, ScreenCaptureError> {
+        let stream = self.screen_capture.start(request, source_id)?;
+        self.display_streams.insert(stream.id.clone(), tab_id);
+        Ok(stream)
+    }
+    
+    /// Stop a display capture stream
+    pub fn stop_display_capture(&mut self, stream_id: &str) {
+        self.screen_capture.stop_stream(stream_id);
+        self.display_streams.remove(stream_id);
+    }
+    
+    /// Next frame of a display capture video track
+    pub fn read_display_frame(&mut self, track_id: &str) -> Option<CapturedFrame> {
+        self.screen_capture.read_frame(track_id)
+    }
+    
+    /// Whether a tab is being captured or is capturing the screen (drives
+    /// the tab strip capture indicator)
+    pub fn is_tab_capturing(&self, tab_id: u64) -> bool {
+        self.screen_capture.active_sources().iter().any(|s| s.tab_id == Some(tab_id))
+            || self.display_streams.iter().any(|(stream, &requester)| {
+                requester == tab_id && self.screen_capture.is_stream_live(stream)
+            })
+            || self.user_streams.iter().any(|(stream, &requester)| {
+                requester == tab_id && self.user_media.is_stream_live(stream)
+            })
+    }
+    
+    /// Hand a tab's composited frame (0xAARRGGBB) to its captures, if any
+    pub fn push_tab_frame(&self, tab_id: u64, pixels: &[u32], width: u32, height: u32) {
+        for (id, sink) in self.screen_capture.captured_tabs() {
+            if id == tab_id {
+                sink.push(CapturedFrame::from_argb(pixels, width, height, Duration::ZERO));
+            }
+        }
+    }
+    
+    /// Whether any tab is being captured
+    pub fn has_tab_captures(&self) -> bool {
+        !self.screen_capture.captured_tabs().is_empty()
+    }
+    
+    /// `mediaDevices.enumerateDevices()`. `exposed` tells which input kinds
+    /// the page may see in full; audio outputs follow microphone access.
+    pub fn enumerate_devices(&self, exposed: impl Fn(MediaDeviceKind) -> bool) -> Vec<MediaDeviceInfo> {
+        let mut devices = self.user_media.enumerate_devices(&exposed);
+        let outputs_exposed = exposed(MediaDeviceKind::AudioInput);
+        for (index, device) in self.audio_devices().into_iter().enumerate() {
+            if !outputs_exposed && index > 0 {
+                break;
+            }
+            devices.push(MediaDeviceInfo {
+                device_id: if outputs_exposed { device.id } else { String::new() },
+                kind: MediaDeviceKind::AudioOutput,
+                label: if outputs_exposed { device.name } else { String::new() },
+                group_id: String::new(),
+            });
+        }
+        devices
+    }
+    
+    /// `getUserMedia` on behalf of `tab_id`; `allowed` is the permission
+    /// decision for each requested kind
+    pub fn get_user_media(
+        &mut self,
+        tab_id: u64,
+        constraints: &MediaStreamConstraints,
+        allowed: impl Fn(MediaDeviceKind) -> bool,
+    ) -> Result<MediaStream, UserMediaError> {
+        let denied = (constraints.audio.is_some() && !allowed(MediaDeviceKind::AudioInput))
+            || (constraints.video.is_some() && !allowed(MediaDeviceKind::VideoInput));
+        if denied {
+            return Err(UserMediaError::NotAllowed);
+        }
+        let stream = self.user_media.get_user_media(constraints)?;
+        self.user_streams.insert(stream.id.clone(), tab_id);
+        Ok(stream)
+    }
+    
+    /// Next camera frame of a `getUserMedia` video track
+    pub fn read_camera_frame(&mut self, track_id: &str) -> Option<CapturedFrame> {
+        self.user_media.read_frame(track_id)
+    }
+    
+    /// Next microphone samples (interleaved) of a `getUserMedia` audio track
+    pub fn read_microphone(&mut self, track_id: &str, out: &mut [f32]) -> usize {
+        self.user_media.read_audio(track_id, out)
+    }
+    
+    /// `track.applyConstraints()` for a camera or microphone track
+    pub fn apply_track_constraints(&mut self, track_id: &str, constraints: &MediaTrackConstraints) -> Result<(), UserMediaError> {
+        self.user_media.apply_constraints(track_id, constraints)
+    }
+    
+    /// `track.getSettings()` for a camera or microphone track
+    pub fn track_settings(&self, track_id: &str) -> Option<&MediaTrackSettings> {
+        self.user_media.settings(track_id)
+    }
+    
+    /// Stop a camera or microphone track
+    pub fn stop_user_track(&mut self, track_id: &str) {
+        self.user_media.stop_track(track_id);
+        self.user_streams.retain(|stream, _| self.user_media.is_stream_live(stream));
+    }
+    
+    /// Stop every track of a `getUserMedia` stream
+    pub fn stop_user_media(&mut self, stream_id: &str) {
+        self.user_media.stop_stream(stream_id);
+        self.user_streams.remove(stream_id);
+    }
+    
+    /// Run `f` on the mixer an element's input lives in
+    fn with_mixer<R>(&self, tab_id: Option<u64>, f: impl FnOnce(&mut AudioMixer) -> R) -> Option<R> {
+        match tab_id.and_then(|id| self.tab_audio.get(&id)) {
+            Some(tab) => {
+                let mut group = tab.group.lock().ok()?;
+                Some(f(group.mixer_mut()))
+            }
+            None => {
+                let mixer = self.audio_engine.lock().ok()?.mixer();
+                let mut mixer = mixer.lock().ok()?;
+                Some(f(&mut mixer))
+            }
+        }
+    }
+    
+    /// Open the audio output device and start the render loop on the audio thread
+    pub fn start_audio_output(&mut self, device_id: Option<&str>) -> Result<(), OutputError> {
+        self.audio_engine.lock().unwrap().start(device_id, OutputConfig::default())?;
+        
+        if self.audio_thread.is_none() {
+            let thread = AudioThread::new();
+            let engine = Arc::clone(&self.audio_engine);
+            let running = Arc::clone(&self.audio_running);
+            running.store(true, Ordering::Release);
+            
+            thread.submit(move || {
+                while running.load(Ordering::Acquire) {
+                    let period = {
+                        let Ok(mut engine) = engine.lock() else { break };
+                        if let Err(e) = engine.tick() {
+                            log::warn!("Audio output error: {}", e);
+                        }
+                        engine.output().config().period()
+                    };
+                    // Wake twice per device period to keep the ring topped up
+                    std::thread::sleep(period / 2);
+                }
+            });
+            self.audio_thread = Some(thread);
+        }
+        
+        log::debug!("Audio output started on {}", self.audio_engine.lock().unwrap().output().backend_name());
+        Ok(())
+    }
+    
+    /// Stop the render loop and close the device
+    pub fn stop_audio_output(&mut self) {
+        self.audio_running.store(false, Ordering::Release);
+        // Dropping the thread joins it
+        self.audio_thread = None;
+        self.audio_engine.lock().unwrap().stop();
+    }
+    
+    /// Available audio output devices
+    pub fn audio_devices(&self) -> Vec<AudioDevice> {
+        self.audio_engine.lock().unwrap().devices()
+    }
+    
+    /// Move audio output to another device (`None` = system default)
+    pub fn select_audio_device(&mut self, device_id: Option<&str>) -> Result<(), OutputError> {
+        let mut engine = self.audio_engine.lock().unwrap();
+        if engine.is_running() {
+            engine.select_device(device_id)
+        } else {
+            drop(engine);
+            self.start_audio_output(device_id)
+        }
+    }
+    
+    /// Whether the audio device is open and rendering
+    pub fn is_audio_output_running(&self) -> bool {
+        self.audio_thread.is_some() && self.audio_engine.lock().unwrap().is_running()
+    }
+    
+    /// Connect an audio element to the output mixer, returning its sample queue
+    fn connect_audio(&mut self, id: u64) -> Option<SharedSampleQueue> {
+        if let Some((_, queue)) = &self.audios.get(&id)?.output {
+            return Some(Arc::clone(queue));
+        }
+        
+        let tab_id = self.current_tab;
+        if let Some(tab) = tab_id {
+            self.tab_group(tab);
+        }
+        
+        let queue: SharedSampleQueue = Default::default();
+        let (volume, muted) = {
+            let audio = self.audios.get(&id)?;
+            (audio.element.base.volume as f32, audio.element.base.muted)
+        };
+        let input = self.with_mixer(tab_id, |mixer| {
+            let input = mixer.add_input(Box::new(Arc::clone(&queue)));
+            mixer.set_gain(input, volume);
+            mixer.set_muted(input, muted);
+            input
+        })?;
+        
+        let audio = self.audios.get_mut(&id)?;
+        audio.output = Some((input, Arc::clone(&queue)));
+        audio.tab_id = tab_id;
+        Some(queue)
+    }
+    
+    /// Push volume/mute state of an audio element to its mixer input
+    fn sync_audio_gain(&self, id: u64) {
+        let Some(audio) = self.audios.get(&id) else { return };
+        let Some((input, _)) = &audio.output else { return };
+        let (input, volume, muted) = (*input, audio.element.base.volume as f32, audio.element.base.muted);
+        self.with_mixer(audio.tab_id, |mixer| {
+            mixer.set_gain(input, volume);
+            mixer.set_muted(input, muted);
+        });
+    }
+    
+    /// Remove all audio elements from their mixers
+    fn disconnect_all_audio(&mut self) {
+        let connected: Vec<(Option<u64>, MixerInputId)> = self.audios.values_mut()
+            .filter_map(|audio| audio.output.take().map(|(input, _)| (audio.tab_id.take(), input)))
+            .collect();
+        for (tab_id, input) in connected {
+            self.with_mixer(tab_id, |mixer| mixer.remove_input(input));
         }
     }
     
     /// Extract media elements from DOM
     pub fn extract_from_document(&mut self, document: &Document) {
         self.videos.clear();
+        self.disconnect_all_audio();
         self.audios.clear();
         
         let tree = document.tree();
@@ -123,6 +572,39 @@ impl MediaManager {
                             }
                         }
                         
+                        // <track> children
+                        for (child_id, _) in tree.children(node_id) {
+                            let Some(child_el) = tree.get(child_id).and_then(|c| c.as_element()) else { continue };
+                            if tree.resolve(child_el.name.local) != "track" {
+                                continue;
+                            }
+                            let mut track = TextTrack::new(TextTrackKind::Subtitles, "", "");
+                            let mut default = false;
+                            for attr in child_el.attrs.iter() {
+                                match tree.resolve(attr.name.local) {
+                                    "kind" => {
+                                        track.kind = match attr.value.to_ascii_lowercase().as_str() {
+                                            "captions" => TextTrackKind::Captions,
+                                            "descriptions" => TextTrackKind::Descriptions,
+                                            "chapters" => TextTrackKind::Chapters,
+                                            "metadata" => TextTrackKind::Metadata,
+                                            _ => TextTrackKind::Subtitles,
+                                        };
+                                    }
+                                    "src" => track.src = Some(attr.value.clone()),
+                                    "srclang" => track.language = attr.value.clone(),
+                                    "label" => track.label = attr.value.clone(),
+                                    "id" => track.id = attr.value.clone(),
+                                    "default" => default = true,
+                                    _ => {}
+                                }
+                            }
+                            if default {
+                                track.mode = TextTrackMode::Showing;
+                            }
+                            video_el.base.add_text_track(track);
+                        }
+                        
                         // Look for <source> children if no src
                         if src.is_empty() {
                             for (child_id, _) in tree.children(node_id) {
@@ -219,6 +701,9 @@ impl MediaManager {
                             element: audio_el,
                             src,
                             loaded: false,
+                            output: None,
+                            tab_id: None,
+                            loader: None,
                         });
                     }
                     
@@ -267,10 +752,11 @@ impl MediaManager {
     pub fn play_video(&mut self, id: u64) -> Result<(), &'static str> {
         if let Some(video) = self.videos.get_mut(&id) {
             video.element.base.play().map_err(|_| "Cannot play")?;
-            Ok(())
         } else {
-            Err("Video not found")
+            return Err("Video not found");
         }
+        self.active_media = Some(ActiveMedia::Video { tab_id: self.current_tab, id });
+        Ok(())
     }
     
     /// Pause video
@@ -284,10 +770,12 @@ impl MediaManager {
     pub fn play_audio(&mut self, id: u64) -> Result<(), &'static str> {
         if let Some(audio) = self.audios.get_mut(&id) {
             audio.element.base.play().map_err(|_| "Cannot play")?;
-            Ok(())
         } else {
-            Err("Audio not found")
+            return Err("Audio not found");
         }
+        self.connect_audio(id);
+        self.active_media = Some(ActiveMedia::Audio { tab_id: self.current_tab, id });
+        Ok(())
     }
     
     /// Pause audio
@@ -297,6 +785,100 @@ impl MediaManager {
         }
     }
     
+    /// Start downloading an audio element's source in byte ranges
+    pub fn load_audio(&mut self, id: u64, now: Duration) {
+        if let Some(audio) = self.audios.get_mut(&id) {
+            audio.loader = Some(ProgressiveLoader::new(&mut audio.element.base, now));
+            audio.loaded = false;
+        }
+    }
+    
+    /// Next byte range to fetch for an audio element's source
+    pub fn audio_fetch_request(&mut self, id: u64) -> Option<Range<u64>> {
+        self.audios.get_mut(&id)?.loader.as_mut()?.next_request()
+    }
+    
+    /// Bytes fetched for an audio element, with the resource length from
+    /// `Content-Range` if known
+    pub fn audio_data_received(&mut self, id: u64, offset: u64, data: &[u8], total: Option<u64>) {
+        if let Some(loader) = self.audios.get_mut(&id).and_then(|a| a.loader.as_mut()) {
+            loader.on_data(offset, data, total);
+        }
+    }
+    
+    /// A range fetch for an audio element failed
+    pub fn audio_fetch_failed(&mut self, id: u64) {
+        if let Some(loader) = self.audios.get_mut(&id).and_then(|a| a.loader.as_mut()) {
+            loader.on_error();
+        }
+    }
+    
+    /// Update an audio element's ready state and buffered ranges from its
+    /// download, returning the events to fire. Autoplay starts once enough
+    /// is buffered.
+    pub fn update_audio_loading(&mut self, id: u64, now: Duration) -> Vec<LoadEvent> {
+        let Some(audio) = self.audios.get_mut(&id) else { return Vec::new() };
+        let Some(loader) = audio.loader.as_mut() else { return Vec::new() };
+        let events = loader.update(&mut audio.element.base, now);
+        audio.loaded = audio.element.base.ready_state == fos_media::ReadyState::HaveEnoughData;
+        if events.contains(&LoadEvent::CanPlay) && audio.element.base.autoplay && audio.element.base.paused {
+            // Autoplay failures leave the element paused
+            let _ = self.play_audio(id);
+        }
+        events
+    }
+    
+    /// Feed fetched WebVTT data to a video's text track
+    pub fn load_text_track(&mut self, video_id: u64, index: usize, data: &str) -> Result<usize, VttError> {
+        let Some(track) = self.videos.get_mut(&video_id)
+            .and_then(|v| v.element.base.text_tracks.get_mut(index)) else {
+            return Ok(0);
+        };
+        let count = track.load_vtt(data)?;
+        log::debug!("Loaded {} cues into text track {} of video {}", count, index, video_id);
+        Ok(count)
+    }
+    
+    /// Update active cues after the video's time changed
+    pub fn update_video_text_tracks(&mut self, video_id: u64) -> Vec<(usize, TextTrackEvent)> {
+        self.videos.get_mut(&video_id)
+            .map(|v| v.element.base.update_text_tracks())
+            .unwrap_or_default()
+    }
+    
+    /// Paint the showing cues of a video over its bounds
+    pub fn paint_video_cues(&self, video_id: u64, canvas: &mut Canvas, text_renderer: &mut TextRenderer, font_id: FontId) {
+        let Some(video) = self.videos.get(&video_id) else { return };
+        let bounds = &video.bounds;
+        let boxes = video.element.base.cue_boxes(
+            bounds.width,
+            bounds.height,
+            &mut RendererMeasure { renderer: text_renderer, font_id },
+        );
+        
+        for cue_box in boxes {
+            for (i, line) in cue_box.lines.iter().enumerate() {
+                let left = bounds.x + cue_box.x + line.x;
+                let top = bounds.y + cue_box.y + i as f32 * cue_box.line_height;
+                canvas.fill_rect(left, top, line.width, cue_box.line_height, Color::rgba(0, 0, 0, 204));
+                let baseline = top + (cue_box.line_height + cue_box.font_size) / 2.0 - cue_box.font_size * 0.2;
+                for run in &line.runs {
+                    let (size, y) = if run.style.ruby_text {
+                        (cue_box.font_size / 2.0, top)
+                    } else {
+                        (cue_box.font_size, baseline)
+                    };
+                    // Karaoke text ahead of the playback position is dimmed
+                    let color = if run.style.future { Color::rgba(160, 160, 160, 255) } else { Color::rgba(255, 255, 255, 255) };
+                    text_renderer.draw_text(canvas, &run.text, left + run.x, y, font_id, size, color);
+                    if run.style.underline {
+                        canvas.fill_rect(left + run.x, baseline + 2.0, run.width, 1.0, color);
+                    }
+                }
+            }
+        }
+    }
+    
     /// Set video volume
     pub fn set_video_volume(&mut self, id: u64, volume: f64) {
         if let Some(video) = self.videos.get_mut(&id) {
@@ -309,6 +891,7 @@ impl MediaManager {
         if let Some(audio) = self.audios.get_mut(&id) {
             audio.element.base.volume = volume.clamp(0.0, 1.0);
         }
+        self.sync_audio_gain(id);
     }
     
     /// Seek video
@@ -321,7 +904,11 @@ impl MediaManager {
     /// Seek audio
     pub fn seek_audio(&mut self, id: u64, time: f64) {
         if let Some(audio) = self.audios.get_mut(&id) {
-            audio.element.base.seek(time);
+            match audio.loader.as_mut() {
+                // Refetches from the seek position if it is not downloaded
+                Some(loader) => loader.seek(&mut audio.element.base, time),
+                None => audio.element.base.seek(time),
+            }
         }
     }
     
@@ -337,6 +924,7 @@ impl MediaManager {
         if let Some(audio) = self.audios.get_mut(&id) {
             audio.element.base.muted = !audio.element.base.muted;
         }
+        self.sync_audio_gain(id);
     }
     
     /// Get media statistics
@@ -370,12 +958,122 @@ impl MediaManager {
     }
 }
 
+/// Camera and microphone access gated by the origin's permissions
+#[cfg(feature = "full")]
+impl MediaManager {
+    fn device_permission(kind: MediaDeviceKind) -> Option<PermissionDescriptor> {
+        match kind {
+            MediaDeviceKind::VideoInput => Some(PermissionDescriptor::new(PermissionName::Camera)),
+            MediaDeviceKind::AudioInput => Some(PermissionDescriptor::new(PermissionName::Microphone)),
+            MediaDeviceKind::AudioOutput => None,
+        }
+    }
+    
+    /// `enumerateDevices()` exposing only kinds the origin was granted
+    pub fn enumerate_devices_for_origin(&self, origin: &str, permissions: &PermissionsManager) -> Vec<MediaDeviceInfo> {
+        self.enumerate_devices(|kind| {
+            Self::device_permission(kind)
+                .is_some_and(|d| permissions.query(origin, &d).state == PermissionState::Granted)
+        })
+    }
+    
+    /// `getUserMedia()` prompting for camera/microphone permission as needed
+    pub fn get_user_media_for_origin(
+        &mut self,
+        tab_id: u64,
+        origin: &str,
+        constraints: &MediaStreamConstraints,
+        permissions: &mut PermissionsManager,
+    ) -> Result<MediaStream, UserMediaError> {
+        let mut granted = Vec::new();
+        let requested = [
+            (constraints.audio.is_some(), MediaDeviceKind::AudioInput),
+            (constraints.video.is_some(), MediaDeviceKind::VideoInput),
+        ];
+        for (_, kind) in requested.into_iter().filter(|(wanted, _)| *wanted) {
+            let Some(descriptor) = Self::device_permission(kind) else { continue };
+            if permissions.request(origin, &descriptor) == PermissionState::Granted {
+                granted.push(kind);
+            }
+        }
+        self.get_user_media(tab_id, constraints, |kind| granted.contains(&kind))
+    }
+}
+
 impl Default for MediaManager {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Media Session and OS media keys
+impl MediaManager {
+    /// `navigator.mediaSession` of a tab
+    pub fn media_session_mut(&mut self, tab_id: u64) -> &mut MediaSession {
+        self.media_sessions.entry(tab_id).or_default()
+    }
+    
+    /// Tab whose media the OS media keys control
+    pub fn media_session_tab(&self) -> Option<u64> {
+        self.active_media.and_then(|m| m.tab_id())
+    }
+    
+    /// Apply pending media key presses and publish the active session.
+    /// Returns actions to dispatch to the page handlers of the returned tab.
+    pub fn poll_media_session(&mut self) -> Option<(u64, Vec<MediaSessionActionDetails>)> {
+        let Some(active) = self.active_media else {
+            self.media_session_controller.clear();
+            return None;
+        };
+        let element = match active {
+            ActiveMedia::Video { id, .. } => self.videos.get_mut(&id).map(|v| &mut v.element.base),
+            ActiveMedia::Audio { id, .. } => self.audios.get_mut(&id).map(|a| &mut a.element.base),
+        };
+        let Some(element) = element else {
+            // Element was removed from the document
+            self.active_media = None;
+            self.media_session_controller.clear();
+            return None;
+        };
+        let tab_id = active.tab_id();
+        let no_session = MediaSession::default();
+        let session = tab_id.and_then(|t| self.media_sessions.get(&t)).unwrap_or(&no_session);
+        let dispatch = self.media_session_controller.poll(session, Some(element));
+        tab_id.map(|t| (t, dispatch))
+    }
+}
+
+/// Media Capabilities
+impl MediaManager {
+    /// `navigator.mediaCapabilities.decodingInfo()`
+    pub fn decoding_info(&self, config: &MediaDecodingConfiguration) -> Result<MediaCapabilitiesInfo, MediaCapabilitiesError> {
+        self.media_capabilities.decoding_info(config)
+    }
+    
+    /// `navigator.mediaCapabilities.encodingInfo()`
+    pub fn encoding_info(&self, config: &MediaEncodingConfiguration) -> Result<MediaCapabilitiesInfo, MediaCapabilitiesError> {
+        self.media_capabilities.encoding_info(config)
+    }
+}
+
+impl Drop for MediaManager {
+    fn drop(&mut self) {
+        self.stop_audio_output();
+    }
+}
+
+/// Cue line breaking with the page's text renderer
+struct RendererMeasure<'a> {
+    renderer: &'a mut TextRenderer,
+    font_id: FontId,
+}
+
+impl TextMeasure for RendererMeasure<'_> {
+    fn measure(&mut self, text: &str, font_size: f32) -> f32 {
+        self.renderer.measure_text(text, self.font_id, font_size)
+    }
+}
+
 /// Media statistics
 #[derive(Debug, Clone)]
 pub struct MediaStats {
@@ -396,6 +1094,112 @@ mod tests {
         assert_eq!(manager.audios.len(), 0);
     }
     
+    #[test]
+    fn test_audio_devices_listed() {
+        let manager = MediaManager::new();
+        assert!(!manager.is_audio_output_running());
+        // Every backend exposes at least the default device
+        assert!(!manager.audio_devices().is_empty());
+    }
+    
+    #[test]
+    fn test_tab_mute_state() {
+        let mut manager = MediaManager::new();
+        assert!(!manager.is_tab_muted(1));
+        manager.set_tab_muted(1, true);
+        assert!(manager.is_tab_muted(1));
+        assert!(!manager.is_tab_audible(1));
+        
+        let capture = manager.capture_tab_audio(1);
+        assert!(capture.is_active());
+        
+        manager.remove_tab(1);
+        assert!(!manager.is_tab_muted(1));
+    }
+    
+    #[test]
+    fn test_tab_capture_indicator() {
+        let mut manager = MediaManager::new();
+        let tabs = vec![(1, "Meeting".to_string()), (2, "Slides".to_string())];
+        let request = manager.request_display_media(DisplayMediaStreamOptions::default(), &tabs, (4, 2)).unwrap();
+        let stream = manager.start_display_capture(1, &request, Some("tab:2")).unwrap();
+        assert!(manager.is_tab_capturing(1));
+        assert!(manager.is_tab_capturing(2));
+        
+        let track = stream.get_video_tracks()[0].id.clone();
+        manager.push_tab_frame(2, &[0xFF000000; 8], 4, 2);
+        assert!(manager.read_display_frame(&track).is_some());
+        
+        manager.remove_tab(2);
+        assert!(!manager.is_tab_capturing(1));
+    }
+    
+    #[test]
+    fn test_user_media_gating() {
+        let mut manager = MediaManager::new();
+        manager.user_media = UserMedia::with_backend(Box::new(fos_media::NullDevices::new()));
+        let constraints = MediaStreamConstraints {
+            video: Some(MediaTrackConstraints::default()),
+            ..Default::default()
+        };
+        assert_eq!(manager.get_user_media(1, &constraints, |_| false).unwrap_err(), UserMediaError::NotAllowed);
+        
+        let hidden = manager.enumerate_devices(|_| false);
+        assert!(hidden.iter().all(|d| d.label.is_empty()));
+        
+        let stream = manager.get_user_media(1, &constraints, |kind| kind == MediaDeviceKind::VideoInput).unwrap();
+        let track = stream.get_video_tracks()[0].id.clone();
+        assert!(manager.is_tab_capturing(1));
+        assert!(manager.read_camera_frame(&track).is_some());
+        assert_eq!(manager.track_settings(&track).unwrap().width, Some(640));
+        
+        manager.stop_user_track(&track);
+        assert!(!manager.is_tab_capturing(1));
+    }
+    
+    #[test]
+    fn test_media_session_follows_playing_element() {
+        let mut manager = MediaManager::new();
+        manager.set_current_tab(1);
+        let mut element = HTMLVideoElement::new();
+        element.base.ready_state = fos_media::ReadyState::HaveEnoughData;
+        manager.videos.insert(5, VideoInstance {
+            id: 5, element, src: "clip.webm".into(), bounds: MediaBounds::default(), loaded: true,
+        });
+        assert!(manager.poll_media_session().is_none());
+        
+        manager.play_video(5).unwrap();
+        manager.media_session_mut(1).metadata = Some(fos_media::MediaMetadata { title: "Clip".into(), ..Default::default() });
+        assert_eq!(manager.poll_media_session(), Some((1, Vec::new())));
+        assert_eq!(manager.media_session_tab(), Some(1));
+        
+        manager.remove_tab(1);
+        assert_eq!(manager.media_session_tab(), None);
+    }
+    
+    #[test]
+    fn test_audio_streaming() {
+        let mut manager = MediaManager::new();
+        manager.audios.insert(3, AudioInstance {
+            id: 3, element: HTMLAudioElement::from_url("song.mp3"), src: "song.mp3".into(),
+            loaded: false, output: None, tab_id: None, loader: None,
+        });
+        manager.load_audio(3, Duration::ZERO);
+        assert_eq!(manager.update_audio_loading(3, Duration::ZERO), [LoadEvent::LoadStart]);
+        
+        // 128 kbps MP3 frames
+        let frame: Vec<u8> = [0xFF, 0xFB, 0x90, 0x00].into_iter().chain([0; 413]).collect();
+        let file = frame.repeat(1000);
+        let range = manager.audio_fetch_request(3).unwrap();
+        assert_eq!(range.start, 0);
+        manager.audio_data_received(3, 0, &file[..100_000], Some(file.len() as u64));
+        let events = manager.update_audio_loading(3, Duration::from_secs(60));
+        assert!(events.contains(&LoadEvent::LoadedMetadata) && events.contains(&LoadEvent::CanPlay));
+        let audio = manager.get_audio(3).unwrap();
+        assert_eq!(audio.element.base.duration, 417_000.0 / 16000.0);
+        assert_eq!(audio.element.base.buffered.end(0), Some(6.25));
+    }
+    
     #[test]
     fn test_media_stats() {
         let manager = MediaManager::new();
@@ -1,10 +1,16 @@
 //! Memory Integration
 //!
 //! Integrates fos-engine memory management: pressure monitoring, tab hibernation, resource deduplication.
+//!
+//! Under pressure, background tabs are hibernated until usage is back below
+//! the hibernation threshold: first tabs over their share of the heap (the
+//! per-tab budget), then the least recently shown. Background tabs over
+//! the page memory limit from about:config are hibernated regardless.
 
 use fos_engine::{
     MemoryManager, MemoryStats, PressureLevel, Arena,
 };
+use crate::tiered::TieredStats;
 
 /// Memory integration for the browser
 pub struct MemoryIntegration {
@@ -16,6 +22,32 @@ pub struct MemoryIntegration {
     pub dedup_enabled: bool,
     /// Last pressure check
     last_check: std::time::Instant,
+    /// Most memory one page may use
+    page_limit: usize,
+}
+
+/// Background tab that could be hibernated
+#[derive(Debug, Clone)]
+pub struct HibernationCandidate {
+    pub tab_id: u32,
+    /// Bytes hibernating would free
+    pub bytes: usize,
+    /// When the tab was last shown
+    pub last_active: std::time::Instant,
+}
+
+/// Memory held by one tab, as about:memory shows it
+///
+/// The shown page is the hot tier, the cached HTML to re-render it from
+/// warm and pages in the back/forward cache cold.
+#[derive(Debug, Clone)]
+pub struct TabMemory {
+    pub tab_id: u32,
+    pub title: String,
+    pub url: String,
+    pub hibernated: bool,
+    pub stats: MemoryStats,
+    pub tiers: TieredStats,
 }
 
 /// Snapshot of tab state for hibernation
@@ -36,9 +68,15 @@ impl MemoryIntegration {
             tab_arena: Arena::new(),
             dedup_enabled: true,
             last_check: std::time::Instant::now(),
+            page_limit: usize::MAX,
         }
     }
     
+    /// Set the most memory one page may use
+    pub fn set_page_limit(&mut self, bytes: usize) {
+        self.page_limit = bytes;
+    }
+    
     /// Update memory statistics
     pub fn update_stats(&mut self, 
         heap_used: usize, 
@@ -81,6 +119,47 @@ impl MemoryIntegration {
         self.last_check = std::time::Instant::now();
     }
     
+    /// Heap share of one tab, at most the page memory limit
+    pub fn tab_budget(&self) -> usize {
+        (self.manager.limits.max_heap / self.manager.limits.tab_limit.max(1)).min(self.page_limit)
+    }
+    
+    /// Bytes to free to get back below the hibernation threshold
+    pub fn excess(&self) -> usize {
+        let limits = &self.manager.limits;
+        let target = (limits.max_heap as f64 * limits.hibernation_threshold) as usize;
+        self.manager.stats.heap_used.saturating_sub(target)
+    }
+    
+    /// Tabs to hibernate to relieve the current pressure; without
+    /// pressure only those over the page memory limit
+    pub fn select_tabs_to_hibernate(&self, mut candidates: Vec<HibernationCandidate>) -> Vec<u32> {
+        if !self.is_under_pressure() {
+            return candidates.iter().filter(|c| c.bytes > self.page_limit).map(|c| c.tab_id).collect();
+        }
+        
+        let budget = self.tab_budget();
+        candidates.sort_by_key(|c| (c.bytes <= budget, c.last_active));
+        
+        let mut needed = self.excess();
+        let mut chosen = Vec::new();
+        for candidate in candidates {
+            if needed == 0 {
+                break;
+            }
+            needed = needed.saturating_sub(candidate.bytes);
+            chosen.push(candidate.tab_id);
+        }
+        chosen
+    }
+    
+    /// Account for memory freed outside the last stats update
+    pub fn release(&mut self, bytes: usize) {
+        let mut stats = self.manager.stats.clone();
+        stats.heap_used = stats.heap_used.saturating_sub(bytes);
+        self.manager.update_stats(stats);
+    }
+    
     /// Hibernate a tab
     pub fn hibernate_tab(&mut self, tab_id: u64, url: &str, title: &str, scroll_y: f64) -> usize {
         let snapshot = TabSnapshot {
@@ -207,4 +286,46 @@ mod tests {
         assert!(result.is_some());
         assert_eq!(mem.hibernated_count(), 0);
     }
+    
+    #[test]
+    fn test_select_tabs_to_hibernate() {
+        use std::time::{Duration, Instant};
+        
+        let mut mem = MemoryIntegration::new();
+        let now = Instant::now();
+        let budget = mem.tab_budget();
+        let candidates = vec![
+            HibernationCandidate { tab_id: 1, bytes: 1024, last_active: now - Duration::from_secs(60) },
+            HibernationCandidate { tab_id: 2, bytes: 1024, last_active: now - Duration::from_secs(600) },
+            HibernationCandidate { tab_id: 3, bytes: budget + 1, last_active: now },
+        ];
+        assert!(mem.select_tabs_to_hibernate(candidates.clone()).is_empty());
+        
+        // Just over the threshold: the over-budget tab alone suffices
+        let threshold = (mem.manager.limits.max_heap as f64 * mem.manager.limits.hibernation_threshold) as usize;
+        mem.update_stats(threshold + budget, 1000, 500);
+        assert_eq!(mem.select_tabs_to_hibernate(candidates.clone()), vec![3]);
+        
+        // Further over, the least recently shown go next
+        mem.update_stats(threshold + budget + 1500, 1000, 500);
+        assert_eq!(mem.select_tabs_to_hibernate(candidates), vec![3, 2, 1]);
+        
+        mem.release(budget + 1500);
+        assert!(!mem.is_under_pressure());
+    }
+    
+    #[test]
+    fn test_page_limit() {
+        let mut mem = MemoryIntegration::new();
+        let now = std::time::Instant::now();
+        let candidates = vec![
+            HibernationCandidate { tab_id: 1, bytes: 1024, last_active: now },
+            HibernationCandidate { tab_id: 2, bytes: 4096, last_active: now },
+        ];
+        assert!(mem.select_tabs_to_hibernate(candidates.clone()).is_empty());
+        
+        mem.set_page_limit(2048);
+        assert_eq!(mem.tab_budget(), 2048);
+        assert_eq!(mem.select_tabs_to_hibernate(candidates), vec![2]);
+    }
 }
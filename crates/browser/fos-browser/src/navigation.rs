@@ -74,6 +74,11 @@ impl History {
         Some(next)
     }
     
+    /// Drop the forward entries, when a frame of the page starts a new one
+    pub fn clear_forward(&mut self) {
+        self.forward.clear();
+    }
+    
     /// Can go back
     pub fn can_go_back(&self) -> bool {
         !self.back.is_empty()
@@ -88,6 +93,26 @@ impl History {
     pub fn current(&self) -> Option<&str> {
         self.current.as_deref()
     }
+    
+    /// Back entries, oldest first
+    pub fn back_entries(&self) -> Vec<String> {
+        self.back.iter().cloned().collect()
+    }
+    
+    /// Forward entries, next first
+    pub fn forward_entries(&self) -> Vec<String> {
+        self.forward.iter().rev().cloned().collect()
+    }
+    
+    /// Rebuild history from saved entries
+    pub fn from_entries(back: &[String], current: &str, forward: &[String]) -> Self {
+        let mut history = Self::new();
+        let skip = back.len().saturating_sub(history.max_size);
+        history.back = back[skip..].iter().cloned().collect();
+        history.current = Some(current.to_string());
+        history.forward = forward.iter().rev().cloned().collect();
+        history
+    }
 }
 
 impl Default for History {
@@ -195,5 +220,11 @@ mod tests {
         
         let next = history.go_forward();
         assert_eq!(next, Some("https://c.com".to_string()));
+        
+        history.go_back();
+        let restored = History::from_entries(&history.back_entries(), "https://b.com", &history.forward_entries());
+        assert_eq!(restored.back_entries(), vec!["https://a.com".to_string()]);
+        assert_eq!(restored.forward_entries(), vec!["https://c.com".to_string()]);
+        assert_eq!(restored.current(), Some("https://b.com"));
     }
 }
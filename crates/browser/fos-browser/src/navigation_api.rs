@@ -0,0 +1,749 @@
+//! Navigation API
+//!
+//! `window.navigation`: the frame's session history as entries with stable
+//! keys and per-entry state, and a `navigate` event through which same-origin
+//! navigations can be intercepted and handled inside the document (SPA
+//! routing) instead of loading a new one.
+//!
+//! A navigation runs in three steps: start it (`navigate`, `reload`,
+//! `traverse_to`, `back`, `forward`) to get the [`NavigateEvent`] to fire,
+//! report what the listeners did with [`Navigation::dispatch_result`], and,
+//! for intercepted navigations, report the settled handler promises with
+//! [`Navigation::finish`]. Cross-document traversals are served from the
+//! [`BackForwardCache`] when the page was kept there.
+
+use fos_engine::url::Url;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Navigation type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavigationType {
+    Push,
+    Replace,
+    Reload,
+    Traverse,
+}
+
+impl NavigationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Push => "push",
+            Self::Replace => "replace",
+            Self::Reload => "reload",
+            Self::Traverse => "traverse",
+        }
+    }
+}
+
+/// `history` option of `navigate()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NavigationHistoryBehavior {
+    /// Push, or replace when navigating to the current URL
+    #[default]
+    Auto,
+    Push,
+    Replace,
+}
+
+/// Options of `navigate()` and `reload()`
+#[derive(Debug, Clone, Default)]
+pub struct NavigateOptions {
+    /// Serialized state for the new entry
+    pub state: Option<String>,
+    /// Value passed to the navigate event, not stored
+    pub info: Option<String>,
+    pub history: NavigationHistoryBehavior,
+}
+
+/// `NavigationHistoryEntry`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavigationHistoryEntry {
+    /// Identifies the slot; kept across replace
+    pub key: String,
+    /// Identifies this entry
+    pub id: String,
+    pub url: String,
+    pub index: usize,
+    /// Document the entry belongs to; same-document entries share it
+    pub document: u64,
+    /// `getState()`
+    pub state: Option<String>,
+    /// `history.state`
+    pub history_state: Option<String>,
+}
+
+/// `NavigationDestination`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavigationDestination {
+    pub url: String,
+    /// Key, id and index of the entry for traversals
+    pub key: Option<String>,
+    pub id: Option<String>,
+    pub index: Option<usize>,
+    pub same_document: bool,
+    pub state: Option<String>,
+}
+
+/// `NavigateEvent` to fire on `window.navigation`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavigateEvent {
+    pub navigation_id: u64,
+    pub navigation_type: NavigationType,
+    pub destination: NavigationDestination,
+    pub can_intercept: bool,
+    pub cancelable: bool,
+    pub user_initiated: bool,
+    pub hash_change: bool,
+    pub info: Option<String>,
+}
+
+/// What the navigate event listeners did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavigateDisposition {
+    /// No listener intervened
+    Proceed,
+    /// `preventDefault()`
+    Canceled,
+    /// `intercept()`; handler promises settle later
+    Intercepted,
+}
+
+/// Page kept alive in the back/forward cache
+#[derive(Debug, Clone)]
+pub struct CachedPage {
+    pub url: String,
+    pub title: String,
+    pub html: String,
+    stored_at: Instant,
+}
+
+impl CachedPage {
+    pub fn new(url: &str, title: &str, html: String) -> Self {
+        Self { url: url.to_string(), title: title.to_string(), html, stored_at: Instant::now() }
+    }
+}
+
+/// What the browser does after the navigate event
+#[derive(Debug, Clone)]
+pub enum NavigationCommit {
+    /// Handled in the current document; the entry is already committed
+    SameDocument,
+    /// Load a new document; `previous_entry` is the entry id to cache the
+    /// outgoing page under
+    Load { url: String, previous_entry: String },
+    /// Show a page restored from the back/forward cache (`pageshow` with persisted)
+    Restore { page: CachedPage, previous_entry: String },
+    /// Canceled by the page
+    Canceled,
+}
+
+/// Event for the page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NavigationEvent {
+    /// `currententrychange`
+    CurrentEntryChange { from_key: String, navigation_type: NavigationType },
+    /// `navigatesuccess`
+    NavigateSuccess { navigation_id: u64 },
+    /// `navigateerror`
+    NavigateError { navigation_id: u64, message: String },
+    /// `dispose` on entries removed from the list
+    Dispose { keys: Vec<String> },
+}
+
+/// Navigation error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NavigationError {
+    /// Unparsable URL
+    Syntax(String),
+    /// Unknown key, no navigation in progress, or nothing to traverse to
+    InvalidState(&'static str),
+    /// Cross-origin URL for intercept() or the History API
+    Security,
+}
+
+impl std::fmt::Display for NavigationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Syntax(url) => write!(f, "Invalid URL: {}", url),
+            Self::InvalidState(msg) => write!(f, "Invalid state: {}", msg),
+            Self::Security => write!(f, "Cross-origin navigation cannot be handled in the document"),
+        }
+    }
+}
+
+impl std::error::Error for NavigationError {}
+
+/// Reason a page was not put in the back/forward cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BfcacheBlocker {
+    UnloadHandler,
+    CacheControlNoStore,
+    OpenWebSocket,
+    ActiveCapture,
+}
+
+/// Back/forward cache of recently left pages, least recently stored evicted first
+#[derive(Debug)]
+pub struct BackForwardCache {
+    pages: HashMap<String, CachedPage>,
+    /// Keys, oldest first
+    order: Vec<String>,
+    /// Why pages were not cached, for `notRestoredReasons`
+    not_restored: HashMap<String, Vec<BfcacheBlocker>>,
+    pub capacity: usize,
+    pub time_to_live: Duration,
+}
+
+impl Default for BackForwardCache {
+    fn default() -> Self {
+        Self {
+            pages: HashMap::new(),
+            order: Vec::new(),
+            not_restored: HashMap::new(),
+            capacity: 6,
+            time_to_live: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl BackForwardCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep a page; returns false when a blocker prevents caching
+    pub fn store(&mut self, key: &str, page: CachedPage, blockers: &[BfcacheBlocker]) -> bool {
+        self.evict(key);
+        if !blockers.is_empty() {
+            self.not_restored.insert(key.to_string(), blockers.to_vec());
+            return false;
+        }
+        self.not_restored.remove(key);
+        self.pages.insert(key.to_string(), page);
+        self.order.push(key.to_string());
+        while self.order.len() > self.capacity {
+            let oldest = self.order.remove(0);
+            self.pages.remove(&oldest);
+        }
+        true
+    }
+
+    /// Remove and return a page that has not expired
+    pub fn take(&mut self, key: &str) -> Option<CachedPage> {
+        let page = self.pages.remove(key)?;
+        self.order.retain(|k| k != key);
+        (page.stored_at.elapsed() < self.time_to_live).then_some(page)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.pages.contains_key(key)
+    }
+
+    pub fn evict(&mut self, key: &str) {
+        if self.pages.remove(key).is_some() {
+            self.order.retain(|k| k != key);
+        }
+    }
+
+    /// Drop pages older than the time to live
+    pub fn evict_expired(&mut self) {
+        let ttl = self.time_to_live;
+        self.pages.retain(|_, page| page.stored_at.elapsed() < ttl);
+        let pages = &self.pages;
+        self.order.retain(|k| pages.contains_key(k));
+    }
+
+    /// Why the page under `key` was not cached
+    pub fn not_restored_reasons(&self, key: &str) -> &[BfcacheBlocker] {
+        self.not_restored.get(key).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn clear(&mut self) {
+        self.pages.clear();
+        self.order.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Approximate bytes held by the cached pages
+    pub fn memory_size(&self) -> usize {
+        self.pages.values().map(|p| p.url.len() + p.title.len() + p.html.len()).sum()
+    }
+}
+
+/// URL with the fragment removed
+fn without_fragment(url: &str) -> &str {
+    url.split_once('#').map(|(u, _)| u).unwrap_or(url)
+}
+
+fn origin(url: &str) -> Option<String> {
+    Url::parse(url).ok().map(|u| u.origin())
+}
+
+/// `window.navigation` of one frame
+#[derive(Debug)]
+pub struct Navigation {
+    entries: Vec<NavigationHistoryEntry>,
+    current: usize,
+    /// Navigation whose navigate event is out, and whether it committed
+    ongoing: Option<(NavigateEvent, bool)>,
+    bfcache: BackForwardCache,
+    events: Vec<NavigationEvent>,
+    next_key: u64,
+    next_navigation_id: u64,
+    next_document: u64,
+}
+
+impl Navigation {
+    pub fn new(initial_url: &str) -> Self {
+        let mut navigation = Self {
+            entries: Vec::new(),
+            current: 0,
+            ongoing: None,
+            bfcache: BackForwardCache::new(),
+            events: Vec::new(),
+            next_key: 1,
+            next_navigation_id: 1,
+            next_document: 2,
+        };
+        let (key, id) = (navigation.new_key(), navigation.new_key());
+        navigation.entries.push(NavigationHistoryEntry {
+            key, id, url: initial_url.to_string(), index: 0, document: 1, state: None, history_state: None,
+        });
+        navigation
+    }
+
+    fn new_key(&mut self) -> String {
+        let key = format!("{:016x}", self.next_key);
+        self.next_key += 1;
+        key
+    }
+
+    /// `entries()`
+    pub fn entries(&self) -> &[NavigationHistoryEntry] {
+        &self.entries
+    }
+
+    /// `currentEntry`
+    pub fn current_entry(&self) -> &NavigationHistoryEntry {
+        &self.entries[self.current]
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.current > 0
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.current + 1 < self.entries.len()
+    }
+
+    /// `transition`: the intercepted navigation still running
+    pub fn transition(&self) -> Option<&NavigateEvent> {
+        self.ongoing.as_ref().filter(|(_, committed)| *committed).map(|(event, _)| event)
+    }
+
+    pub fn bfcache(&self) -> &BackForwardCache {
+        &self.bfcache
+    }
+
+    pub fn bfcache_mut(&mut self) -> &mut BackForwardCache {
+        &mut self.bfcache
+    }
+
+    /// `updateCurrentEntry({ state })`
+    pub fn update_current_entry(&mut self, state: Option<String>) {
+        self.entries[self.current].state = state;
+    }
+
+    fn resolve(&self, url: &str) -> Result<String, NavigationError> {
+        if let Ok(parsed) = Url::parse(url) {
+            return Ok(parsed.to_string());
+        }
+        Url::parse(&self.current_entry().url)
+            .and_then(|base| base.join(url))
+            .map(|u| u.to_string())
+            .map_err(|_| NavigationError::Syntax(url.to_string()))
+    }
+
+    fn same_origin(&self, url: &str) -> bool {
+        origin(url).is_some_and(|o| Some(o) == origin(&self.current_entry().url))
+    }
+
+    /// Fire a navigation; a previous one still in progress is aborted
+    fn start(&mut self, mut event: NavigateEvent) -> NavigateEvent {
+        if let Some((previous, _)) = self.ongoing.take() {
+            self.events.push(NavigationEvent::NavigateError {
+                navigation_id: previous.navigation_id,
+                message: "AbortError: navigation superseded".into(),
+            });
+        }
+        event.navigation_id = self.next_navigation_id;
+        self.next_navigation_id += 1;
+        self.ongoing = Some((event.clone(), false));
+        event
+    }
+
+    /// `navigate(url, options)`
+    pub fn navigate(&mut self, url: &str, options: NavigateOptions, user_initiated: bool) -> Result<NavigateEvent, NavigationError> {
+        let url = self.resolve(url)?;
+        let current = &self.current_entry().url;
+        let navigation_type = match options.history {
+            NavigationHistoryBehavior::Replace => NavigationType::Replace,
+            NavigationHistoryBehavior::Push => NavigationType::Push,
+            NavigationHistoryBehavior::Auto if url == *current => NavigationType::Replace,
+            NavigationHistoryBehavior::Auto => NavigationType::Push,
+        };
+        let hash_change = url != *current && url.contains('#') && without_fragment(&url) == without_fragment(current);
+        let can_intercept = self.same_origin(&url);
+        Ok(self.start(NavigateEvent {
+            navigation_id: 0,
+            navigation_type,
+            destination: NavigationDestination {
+                url, key: None, id: None, index: None, same_document: hash_change, state: options.state,
+            },
+            can_intercept,
+            cancelable: true,
+            user_initiated,
+            hash_change,
+            info: options.info,
+        }))
+    }
+
+    /// `reload(options)`
+    pub fn reload(&mut self, options: NavigateOptions, user_initiated: bool) -> NavigateEvent {
+        let entry = self.current_entry();
+        let destination = NavigationDestination {
+            url: entry.url.clone(),
+            key: None,
+            id: None,
+            index: None,
+            same_document: false,
+            state: options.state.or_else(|| entry.state.clone()),
+        };
+        self.start(NavigateEvent {
+            navigation_id: 0,
+            navigation_type: NavigationType::Reload,
+            destination,
+            can_intercept: true,
+            cancelable: true,
+            user_initiated,
+            hash_change: false,
+            info: options.info,
+        })
+    }
+
+    /// `traverseTo(key, { info })`
+    pub fn traverse_to(&mut self, key: &str, info: Option<String>, user_initiated: bool) -> Result<NavigateEvent, NavigationError> {
+        let index = self.entries.iter().position(|e| e.key == key)
+            .ok_or(NavigationError::InvalidState("no entry with this key"))?;
+        if index == self.current {
+            return Err(NavigationError::InvalidState("entry is already current"));
+        }
+        let entry = &self.entries[index];
+        let same_document = entry.document == self.current_entry().document;
+        let destination = NavigationDestination {
+            url: entry.url.clone(),
+            key: Some(entry.key.clone()),
+            id: Some(entry.id.clone()),
+            index: Some(index),
+            same_document,
+            state: entry.state.clone(),
+        };
+        let hash_change = same_document && without_fragment(&entry.url) == without_fragment(&self.current_entry().url)
+            && entry.url != self.current_entry().url;
+        // Cross-document traversals cannot be intercepted or canceled
+        Ok(self.start(NavigateEvent {
+            navigation_id: 0,
+            navigation_type: NavigationType::Traverse,
+            destination,
+            can_intercept: same_document,
+            cancelable: same_document,
+            user_initiated,
+            hash_change,
+            info,
+        }))
+    }
+
+    /// `back()`
+    pub fn back(&mut self, info: Option<String>, user_initiated: bool) -> Result<NavigateEvent, NavigationError> {
+        let key = self.current.checked_sub(1).map(|i| self.entries[i].key.clone())
+            .ok_or(NavigationError::InvalidState("no previous entry"))?;
+        self.traverse_to(&key, info, user_initiated)
+    }
+
+    /// `forward()`
+    pub fn forward(&mut self, info: Option<String>, user_initiated: bool) -> Result<NavigateEvent, NavigationError> {
+        let key = self.entries.get(self.current + 1).map(|e| e.key.clone())
+            .ok_or(NavigationError::InvalidState("no next entry"))?;
+        self.traverse_to(&key, info, user_initiated)
+    }
+
+    /// Report what the navigate event listeners did
+    pub fn dispatch_result(&mut self, navigation_id: u64, disposition: NavigateDisposition) -> Result<NavigationCommit, NavigationError> {
+        let (event, _) = self.ongoing.clone()
+            .filter(|(e, committed)| e.navigation_id == navigation_id && !committed)
+            .ok_or(NavigationError::InvalidState("no such navigation in progress"))?;
+
+        match disposition {
+            NavigateDisposition::Canceled if event.cancelable => {
+                self.ongoing = None;
+                self.events.push(NavigationEvent::NavigateError {
+                    navigation_id,
+                    message: "AbortError: navigation canceled".into(),
+                });
+                Ok(NavigationCommit::Canceled)
+            }
+            NavigateDisposition::Intercepted => {
+                if !event.can_intercept {
+                    return Err(NavigationError::Security);
+                }
+                self.commit(&event, true);
+                self.ongoing = Some((event, true));
+                Ok(NavigationCommit::SameDocument)
+            }
+            // A non-cancelable event ignores preventDefault()
+            NavigateDisposition::Proceed | NavigateDisposition::Canceled => {
+                self.ongoing = None;
+                if event.destination.same_document {
+                    self.commit(&event, true);
+                    self.events.push(NavigationEvent::NavigateSuccess { navigation_id });
+                    return Ok(NavigationCommit::SameDocument);
+                }
+                let previous_entry = self.current_entry().id.clone();
+                self.commit(&event, false);
+                let entry = self.current_entry();
+                let restored = match event.navigation_type {
+                    NavigationType::Traverse => {
+                        let id = entry.id.clone();
+                        self.bfcache.take(&id)
+                    }
+                    _ => None,
+                };
+                Ok(match restored {
+                    Some(page) => NavigationCommit::Restore { page, previous_entry },
+                    None => NavigationCommit::Load { url: event.destination.url.clone(), previous_entry },
+                })
+            }
+        }
+    }
+
+    /// Intercept handlers settled
+    pub fn finish(&mut self, navigation_id: u64, result: Result<(), String>) {
+        if !self.ongoing.as_ref().is_some_and(|(e, committed)| e.navigation_id == navigation_id && *committed) {
+            return;
+        }
+        self.ongoing = None;
+        self.events.push(match result {
+            Ok(()) => NavigationEvent::NavigateSuccess { navigation_id },
+            Err(message) => NavigationEvent::NavigateError { navigation_id, message },
+        });
+    }
+
+    /// Update the entry list for a committed navigation
+    fn commit(&mut self, event: &NavigateEvent, same_document: bool) {
+        let from_key = self.current_entry().key.clone();
+        let document = if same_document {
+            self.current_entry().document
+        } else {
+            self.next_document += 1;
+            self.next_document - 1
+        };
+        match event.navigation_type {
+            NavigationType::Push => {
+                self.dispose_after(self.current);
+                let (key, id) = (self.new_key(), self.new_key());
+                self.entries.push(NavigationHistoryEntry {
+                    key, id, url: event.destination.url.clone(), index: self.entries.len(),
+                    document, state: event.destination.state.clone(), history_state: None,
+                });
+                self.current = self.entries.len() - 1;
+            }
+            NavigationType::Replace => {
+                let id = self.new_key();
+                let entry = &mut self.entries[self.current];
+                self.bfcache.evict(&entry.id);
+                entry.id = id;
+                entry.url = event.destination.url.clone();
+                entry.document = document;
+                entry.state = event.destination.state.clone();
+                entry.history_state = None;
+            }
+            NavigationType::Reload => {
+                let entry = &mut self.entries[self.current];
+                entry.document = document;
+                entry.state = event.destination.state.clone();
+            }
+            NavigationType::Traverse => {
+                if let Some(index) = event.destination.index {
+                    self.current = index;
+                }
+            }
+        }
+        if same_document {
+            self.events.push(NavigationEvent::CurrentEntryChange { from_key, navigation_type: event.navigation_type });
+        }
+    }
+
+    /// Remove entries after `index`, dropping their cached pages
+    fn dispose_after(&mut self, index: usize) {
+        let removed: Vec<NavigationHistoryEntry> = self.entries.drain(index + 1..).collect();
+        if removed.is_empty() {
+            return;
+        }
+        for entry in &removed {
+            self.bfcache.evict(&entry.id);
+        }
+        self.events.push(NavigationEvent::Dispose { keys: removed.into_iter().map(|e| e.key).collect() });
+    }
+
+    // === History API ===
+
+    /// `history.pushState(state, "", url)`: a same-document push
+    pub fn push_state(&mut self, history_state: Option<String>, url: Option<&str>) -> Result<(), NavigationError> {
+        let url = match url {
+            Some(url) => self.resolve(url)?,
+            None => self.current_entry().url.clone(),
+        };
+        if !self.same_origin(&url) && url != self.current_entry().url {
+            return Err(NavigationError::Security);
+        }
+        let from_key = self.current_entry().key.clone();
+        let document = self.current_entry().document;
+        self.dispose_after(self.current);
+        let (key, id) = (self.new_key(), self.new_key());
+        self.entries.push(NavigationHistoryEntry {
+            key, id, url, index: self.entries.len(), document, state: None, history_state,
+        });
+        self.current = self.entries.len() - 1;
+        self.events.push(NavigationEvent::CurrentEntryChange { from_key, navigation_type: NavigationType::Push });
+        Ok(())
+    }
+
+    /// `history.replaceState(state, "", url)`
+    pub fn replace_state(&mut self, history_state: Option<String>, url: Option<&str>) -> Result<(), NavigationError> {
+        let url = match url {
+            Some(url) => self.resolve(url)?,
+            None => self.current_entry().url.clone(),
+        };
+        if !self.same_origin(&url) && url != self.current_entry().url {
+            return Err(NavigationError::Security);
+        }
+        let from_key = self.current_entry().key.clone();
+        let id = self.new_key();
+        let entry = &mut self.entries[self.current];
+        entry.id = id;
+        entry.url = url;
+        entry.history_state = history_state;
+        self.events.push(NavigationEvent::CurrentEntryChange { from_key, navigation_type: NavigationType::Replace });
+        Ok(())
+    }
+
+    /// `history.state`
+    pub fn history_state(&self) -> Option<&str> {
+        self.current_entry().history_state.as_deref()
+    }
+
+    /// `history.go(delta)`
+    pub fn go(&mut self, delta: i32) -> Result<NavigateEvent, NavigationError> {
+        let index = self.current as i64 + delta as i64;
+        let key = usize::try_from(index).ok()
+            .and_then(|i| self.entries.get(i))
+            .map(|e| e.key.clone())
+            .ok_or(NavigationError::InvalidState("no entry at this offset"))?;
+        self.traverse_to(&key, None, false)
+    }
+
+    pub fn take_events(&mut self) -> Vec<NavigationEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intercepted_navigation() {
+        let mut nav = Navigation::new("https://app.example/");
+        let first_key = nav.current_entry().key.clone();
+
+        let event = nav.navigate("/inbox", NavigateOptions { state: Some("{\"tab\":1}".into()), ..Default::default() }, false).unwrap();
+        assert_eq!(event.navigation_type, NavigationType::Push);
+        assert!(event.can_intercept);
+        assert!(matches!(nav.dispatch_result(event.navigation_id, NavigateDisposition::Intercepted), Ok(NavigationCommit::SameDocument)));
+        assert_eq!(nav.current_entry().url, "https://app.example/inbox");
+        assert_eq!(nav.current_entry().state.as_deref(), Some("{\"tab\":1}"));
+        assert!(nav.transition().is_some());
+        nav.finish(event.navigation_id, Ok(()));
+        assert!(nav.transition().is_none());
+        assert_eq!(nav.take_events(), vec![
+            NavigationEvent::CurrentEntryChange { from_key: first_key.clone(), navigation_type: NavigationType::Push },
+            NavigationEvent::NavigateSuccess { navigation_id: event.navigation_id },
+        ]);
+
+        // Same-document traversal back to the first entry
+        let event = nav.traverse_to(&first_key, None, true).unwrap();
+        assert!(event.destination.same_document);
+        nav.dispatch_result(event.navigation_id, NavigateDisposition::Proceed).unwrap();
+        assert_eq!(nav.current_entry().key, first_key);
+        assert!(nav.can_go_forward());
+
+        // Cross-origin navigations cannot be intercepted
+        let event = nav.navigate("https://other.example/", NavigateOptions::default(), false).unwrap();
+        assert!(!event.can_intercept);
+        assert_eq!(nav.dispatch_result(event.navigation_id, NavigateDisposition::Intercepted).unwrap_err(), NavigationError::Security);
+        assert!(matches!(nav.dispatch_result(event.navigation_id, NavigateDisposition::Canceled), Ok(NavigationCommit::Canceled)));
+        assert_eq!(nav.entries().len(), 2);
+    }
+
+    #[test]
+    fn test_history_api_and_bfcache() {
+        let mut nav = Navigation::new("https://a.example/");
+        nav.push_state(Some("1".into()), Some("/page1")).unwrap();
+        assert_eq!(nav.history_state(), Some("1"));
+        assert_eq!(nav.push_state(None, Some("https://b.example/")), Err(NavigationError::Security));
+
+        // Cross-document push, then come back to the page through the cache
+        let event = nav.navigate("https://b.example/", NavigateOptions::default(), true).unwrap();
+        let NavigationCommit::Load { previous_entry, .. } = nav.dispatch_result(event.navigation_id, NavigateDisposition::Proceed).unwrap() else {
+            panic!("expected a document load");
+        };
+        nav.bfcache_mut().store(&previous_entry, CachedPage::new("https://a.example/page1", "Page 1", "<p>1</p>".into()), &[]);
+
+        let event = nav.back(None, true).unwrap();
+        assert!(!event.cancelable);
+        match nav.dispatch_result(event.navigation_id, NavigateDisposition::Canceled).unwrap() {
+            NavigationCommit::Restore { page, .. } => assert_eq!(page.html, "<p>1</p>"),
+            other => panic!("expected a bfcache restore, got {:?}", other),
+        }
+        assert_eq!(nav.history_state(), Some("1"));
+
+        // Pushing drops the forward entries and their cached pages
+        nav.push_state(None, Some("/page2")).unwrap();
+        assert_eq!(nav.entries().len(), 3);
+        assert!(matches!(nav.take_events().last(), Some(NavigationEvent::CurrentEntryChange { .. })));
+        assert!(nav.go(5).is_err());
+    }
+
+    #[test]
+    fn test_bfcache_blockers_and_capacity() {
+        let mut cache = BackForwardCache::new();
+        cache.capacity = 2;
+        assert!(!cache.store("a", CachedPage::new("https://a/", "", String::new()), &[BfcacheBlocker::UnloadHandler]));
+        assert_eq!(cache.not_restored_reasons("a"), &[BfcacheBlocker::UnloadHandler]);
+        for key in ["b", "c", "d"] {
+            assert!(cache.store(key, CachedPage::new("https://a/", "", String::new()), &[]));
+        }
+        assert!(!cache.contains("b"));
+        assert!(cache.take("d").is_some());
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.memory_size(), "https://a/".len());
+    }
+}
@@ -1,19 +1,32 @@
 //! Enhanced Networking Layer
 //!
 //! Integrates fos-net for HTTP caching, HTTP/2, and security.
+//! Preference headers (Save-Data, Sec-GPC, DNT) are assembled in one
+//! place, the client a fetch goes out with. Hosts with an HSTS policy are
+//! only reached over HTTPS and never get certificate error overrides.
+//! Credentials accepted by servers and proxies are shared by every client.
+//! Responses are cached in an index that can be shared with other tabs, so
+//! a URL is fetched or revalidated once however many tabs want it, and is
+//! held in memory once. Third-party scripts and fonts the user blocked
+//! never leave the browser.
 
 use std::time::Duration;
 use std::collections::HashMap;
-use fos_net::cache::HttpCache;
+use std::sync::{Arc, Mutex};
+use fos_net::cache::CacheValidators;
 use fos_net::http2::Http2Connection;
-use fos_net::network_opt::{PredictiveDns, RequestCoalescer};
-use fos_security::https::{SecureContext, MixedContentChecker, MixedContentResult};
+use fos_net::network_opt::{CrossTabCache, CrossTabLookup, PredictiveDns, RequestCoalescer};
+use fos_net::{AuthCache, ClientCertManager, CredentialProvider, InterceptorChain, NetError, Request, RequestInterceptor, RequestTimings, ResourceType, TlsError};
+use fos_devtools::ResponseOverride;
+use fos_security::https::{SecureContext, MixedContentChecker, MixedContentResult, HstsStore};
+use fos_security::{ContentBlocking, ContentCategory, PrivacySignals};
+use crate::cert_overrides::CertificateOverrides;
 
 /// Network manager for the browser
 /// Integrates HTTP caching, HTTP/2 multiplexing, predictive DNS, and security
 pub struct NetworkManager {
-    /// HTTP response cache
-    cache: HttpCache,
+    /// HTTP response cache, possibly shared with other tabs
+    cache: CrossTabCache,
     /// Mixed content checker
     mixed_content: MixedContentChecker,
     /// User agent string
@@ -24,14 +37,41 @@ pub struct NetworkManager {
     predictive_dns: PredictiveDns,
     /// Request coalescer for batching
     coalescer: RequestCoalescer,
+    /// DevTools overrides, answered before the cache and network
+    overrides: Vec<ResponseOverride>,
+    /// Request rules and other stages that see requests after overrides
+    /// and before the cache
+    interceptors: InterceptorChain,
+    /// Data saver is on: requests carry `Save-Data: on`
+    save_data: bool,
+    /// Global Privacy Control and Do Not Track headers, from preferences
+    /// that may change at any time
+    privacy_signals: Arc<Mutex<PrivacySignals>>,
+    /// Third-party content categories blocked, by default and per site
+    content_blocking: ContentBlocking,
+    /// Hosts only reached over HTTPS
+    hsts: HstsStore,
+    /// Hosts connected to despite certificate errors
+    certificate_overrides: CertificateOverrides,
+    /// HTTP proxy as `host:port`
+    proxy: Option<String>,
+    /// Credentials accepted by origins and the proxy
+    auth_cache: AuthCache,
+    /// Answers 401 and 407 challenges
+    credential_provider: Option<Arc<dyn CredentialProvider>>,
+    /// Certificates for sites that ask for one, and the choices per host
+    client_certs: Option<Arc<ClientCertManager>>,
+    /// Negotiate and NTLM with the system login, for servers policy allows
+    #[cfg(feature = "integrated-auth")]
+    integrated_auth: Option<fos_net::IntegratedAuth>,
 }
 
 impl NetworkManager {
     /// Create a new network manager with all fos-net features
     pub fn new() -> Self {
         Self {
-            // 500 entries, 25MB cache
-            cache: HttpCache::new(500, 25 * 1024 * 1024),
+            // 25MB cache
+            cache: CrossTabCache::new(),
             mixed_content: MixedContentChecker::new(),
             user_agent: format!(
                 "fOS-Browser/0.1 (compatible; fOS Engine; +https://github.com/fosproject)"
@@ -39,9 +79,127 @@ impl NetworkManager {
             http2_pool: HashMap::new(),
             predictive_dns: PredictiveDns::new(),
             coalescer: RequestCoalescer::new(5, 50), // Batch 5 requests or 50ms
+            overrides: Vec::new(),
+            interceptors: InterceptorChain::new(),
+            save_data: false,
+            privacy_signals: Arc::new(Mutex::new(PrivacySignals::new())),
+            content_blocking: ContentBlocking::new(),
+            hsts: HstsStore::new(),
+            certificate_overrides: CertificateOverrides::new(),
+            proxy: None,
+            auth_cache: AuthCache::new(),
+            credential_provider: None,
+            client_certs: None,
+            #[cfg(feature = "integrated-auth")]
+            integrated_auth: None,
         }
     }
     
+    /// Share `cache` with the other tabs' network managers
+    pub fn with_cross_tab_cache(mut self, cache: CrossTabCache) -> Self {
+        self.cache = cache;
+        self
+    }
+    
+    /// The cache this manager shares, for other tabs to join
+    pub fn cross_tab_cache(&self) -> CrossTabCache {
+        self.cache.clone()
+    }
+    
+    /// Follow the data saver preference for later requests
+    pub fn set_save_data(&mut self, enabled: bool) {
+        self.save_data = enabled;
+    }
+    
+    /// Follow the shared privacy signal preferences, as they change
+    pub fn set_privacy_signals(&mut self, signals: Arc<Mutex<PrivacySignals>>) {
+        self.privacy_signals = signals;
+    }
+    
+    /// Follow the content blocking settings for later requests
+    pub fn set_content_blocking(&mut self, blocking: ContentBlocking) {
+        self.content_blocking = blocking;
+    }
+    
+    /// Replace the certificate error overrides, e.g. with ones saved to disk
+    pub fn set_certificate_overrides(&mut self, overrides: CertificateOverrides) {
+        self.certificate_overrides = overrides;
+    }
+    
+    /// Connect to `host` from now on despite the certificate error `code`.
+    /// Refused for hosts with an HSTS policy.
+    pub fn allow_invalid_certificate(&mut self, host: &str, code: &str) -> bool {
+        if self.hsts.is_hsts_host(host) {
+            return false;
+        }
+        self.certificate_overrides.add(host, code);
+        true
+    }
+    
+    /// Whether `host` is only reached over HTTPS
+    pub fn is_hsts_host(&self, host: &str) -> bool {
+        self.hsts.is_hsts_host(host)
+    }
+    
+    /// Send requests through an HTTP proxy, or connect directly with `None`
+    pub fn set_proxy(&mut self, proxy: Option<&str>) {
+        self.proxy = proxy.map(String::from);
+    }
+    
+    /// Ask `provider` for credentials when a server or the proxy challenges
+    pub fn set_credential_provider(&mut self, provider: Arc<dyn CredentialProvider>) {
+        self.credential_provider = Some(provider);
+    }
+    
+    /// Answer sites asking for a client certificate from `manager`
+    pub fn set_client_certificates(&mut self, manager: Arc<ClientCertManager>) {
+        self.client_certs = Some(manager);
+    }
+    
+    /// Sign in to the servers in the comma-separated `allowed_servers` list
+    /// with the system login when they ask for Negotiate or NTLM. False if
+    /// the system has no security library for it.
+    #[cfg(feature = "integrated-auth")]
+    pub fn set_integrated_auth_servers(&mut self, allowed_servers: &str) -> bool {
+        let policy = fos_net::IntegratedAuthPolicy::new().with_allowed_servers(allowed_servers);
+        self.integrated_auth = fos_net::IntegratedAuth::platform(policy);
+        self.integrated_auth.is_some()
+    }
+    
+    /// Forget credentials entered for HTTP authentication this session
+    pub fn clear_http_auth(&mut self) {
+        self.auth_cache.clear();
+    }
+    
+    /// Client for a request made on behalf of a page at `site_url`, sending
+    /// the user's preference headers with it and its redirects
+    fn client(&self, site_url: &str) -> Result<fos_net::client::blocking::Client, NetworkError> {
+        let mut builder = fos_net::client::blocking::Client::builder()
+            .user_agent(&self.user_agent)
+            .save_data(self.save_data);
+        for (name, value) in self.privacy_signals.lock().unwrap().headers(site_url) {
+            builder = builder.default_header(name, value);
+        }
+        for host in self.certificate_overrides.hosts() {
+            builder = builder.allow_invalid_certificate(host);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy);
+        }
+        builder = builder.auth_cache(self.auth_cache.clone());
+        if let Some(provider) = &self.credential_provider {
+            builder = builder.credential_provider(provider.clone());
+        }
+        if let Some(manager) = &self.client_certs {
+            builder = builder.client_certificates(manager.clone());
+        }
+        #[cfg(feature = "integrated-auth")]
+        if let Some(auth) = &self.integrated_auth {
+            builder = builder.integrated_auth(auth.clone());
+        }
+        builder.build().map_err(|e| NetworkError::RequestFailed(format!("{}", e)))
+    }
+    
     // === HTTP/2 Connection Pool ===
     
     /// Get or create HTTP/2 connection for a host
@@ -80,19 +238,92 @@ impl NetworkManager {
         self.predictive_dns.pop_prefetch()
     }
     
+    // === Interception ===
+    
+    /// Replace the local response overrides
+    pub fn set_response_overrides(&mut self, overrides: Vec<ResponseOverride>) {
+        self.overrides = overrides;
+    }
+    
+    /// Add a stage to the interception pipeline
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn RequestInterceptor>) -> Self {
+        self.interceptors.add(interceptor);
+        self
+    }
+    
+    /// Answer a request from an override, longest pattern first
+    fn intercept(&self, url: &str) -> Option<FetchResult> {
+        let response = self.overrides.iter()
+            .filter(|o| o.matches(url))
+            .max_by_key(|o| o.url_pattern.len())?;
+        log::debug!("Override for {}", url);
+        let content_type = response.headers.get("content-type").cloned()
+            .unwrap_or_else(|| "text/html".to_string());
+        let mut headers: Vec<(String, String)> = response.headers.iter()
+            .map(|(n, v)| (n.clone(), v.clone()))
+            .collect();
+        headers.sort();
+        Some(FetchResult {
+            body: response.body.clone(),
+            content_type,
+            from_cache: false,
+            status: response.status_code,
+            headers,
+            timings: None,
+            overridden: true,
+        })
+    }
+    
     // === Fetch with caching ===
     
     /// Fetch a URL with caching
     pub fn fetch(&mut self, url: &str, page_url: Option<&str>) -> Result<FetchResult, NetworkError> {
-        // Check cache first
-        if let Some(entry) = self.cache.get(url) {
-            log::debug!("Cache hit for {}", url);
-            return Ok(FetchResult {
-                body: entry.body.clone(),
-                content_type: entry.content_type.clone(),
-                from_cache: true,
-                status: 200,
-            });
+        self.fetch_as(url, page_url, ResourceType::from_hints(url, None), &[])
+    }
+    
+    /// Fetch a page navigated to with extra request headers, such as a
+    /// service worker's navigation preload header
+    pub fn fetch_navigation(&mut self, url: &str, headers: &[(String, String)]) -> Result<FetchResult, NetworkError> {
+        self.fetch_as(url, None, ResourceType::from_hints(url, None), headers)
+    }
+    
+    fn fetch_as(&mut self, url: &str, page_url: Option<&str>, resource_type: ResourceType, headers: &[(String, String)]) -> Result<FetchResult, NetworkError> {
+        // Overrides take precedence over everything
+        if let Some(result) = self.intercept(url) {
+            return Ok(result);
+        }
+        
+        // Third-party scripts and fonts the page may not load
+        if let Some(page) = page_url {
+            let category = match resource_type {
+                ResourceType::Script => Some(ContentCategory::Scripts),
+                ResourceType::Font => Some(ContentCategory::Fonts),
+                _ => None,
+            };
+            if category.is_some_and(|category| self.content_blocking.should_block(url, page, category)) {
+                log::debug!("Blocked third-party {:?} {}", category, url);
+                return Err(NetworkError::Blocked(url.to_string()));
+            }
+        }
+        
+        // Then the interception pipeline, which may block or redirect
+        let mut request = Request::get(url).with_resource_type(resource_type);
+        for (name, value) in headers {
+            request = request.with_header(name, value);
+        }
+        if let Some(page) = page_url {
+            request = request.with_initiator(page);
+        }
+        let request = self.interceptors.apply_request(request).map_err(|e| match e {
+            NetError::Blocked(url) => NetworkError::Blocked(url),
+            e => NetworkError::RequestFailed(e.to_string()),
+        })?;
+        let url = request.url.as_str();
+        
+        // HSTS hosts are only reached over HTTPS
+        if let Some(upgraded) = self.hsts.upgrade(url) {
+            log::debug!("HSTS upgrade to {}", upgraded);
+            return self.fetch_as(&upgraded, page_url, resource_type, headers);
         }
         
         // Check mixed content if we have a page context
@@ -112,7 +343,7 @@ impl NetworkManager {
                         if let Some(upgraded) = url.strip_prefix("http://") {
                             let new_url = format!("https://{}", upgraded);
                             log::info!("Upgraded to HTTPS: {}", new_url);
-                            return self.fetch(&new_url, page_url);
+                            return self.fetch_as(&new_url, page_url, resource_type, headers);
                         }
                     }
                     MixedContentResult::Warn => {
@@ -131,19 +362,52 @@ impl NetworkManager {
             }
         }
         
+        // Then the cache; while another tab loads this URL, wait for its
+        // answer instead of asking again
+        let load = match self.cache.lookup(url) {
+            CrossTabLookup::Fresh(resource) => {
+                log::debug!("Cache hit for {}", url);
+                return Ok(FetchResult {
+                    body: resource.data.to_vec(),
+                    content_type: resource.content_type.clone(),
+                    from_cache: true,
+                    status: 200,
+                    headers: vec![("content-type".to_string(), resource.content_type.clone())],
+                    timings: None,
+                    overridden: false,
+                });
+            }
+            CrossTabLookup::Load(load) => load,
+        };
+        
         // Fetch from network
         log::debug!("Fetching from network: {}", url);
         
-        let mut client = fos_net::client::blocking::Client::new();
+        let mut client = self.client(page_url.unwrap_or(url))?;
         
-        let response = client.get(url)
-            .map_err(|e| NetworkError::RequestFailed(format!("{}", e)))?;
-        
-        let status = response.status;
+        let mut headers: Vec<(String, String)> = request.headers.iter()
+            .map(|(n, v)| (n.clone(), v.clone()))
+            .collect();
         
-        if !response.is_success() {
-            return Err(NetworkError::HttpError(status));
+        // Revalidate the stale copy, whichever tab stored it
+        if let Some(validators) = load.validators() {
+            if let Some(etag) = &validators.etag {
+                headers.push(("If-None-Match".to_string(), etag.clone()));
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                headers.push(("If-Modified-Since".to_string(), last_modified.clone()));
+            }
         }
+        let mut response = client.request(request.method.as_str(), url, Some(headers), None)
+            .map_err(|e| match e {
+                NetError::Tls(e) => NetworkError::Tls(e),
+                e => NetworkError::RequestFailed(format!("{}", e)),
+            })?;
+        self.record_hsts(url, &response.headers);
+        self.interceptors.apply_response(&request, &mut response.headers);
+        let timings = client.last_timings();
+        
+        let status = response.status;
         
         // Parse cache headers
         let cache_control = response.headers.iter()
@@ -151,10 +415,36 @@ impl NetworkManager {
             .map(|(_, v)| v.clone())
             .unwrap_or_default();
         
+        // Determine cache TTL
+        let max_age = parse_max_age(&cache_control).unwrap_or(Duration::from_secs(300));
+        
+        // The cached copy is still good, for every tab
+        if status == 304 && load.validators().is_some() {
+            let resource = load.not_modified(max_age).ok_or(NetworkError::HttpError(status))?;
+            log::debug!("Revalidated {}", url);
+            return Ok(FetchResult {
+                body: resource.data.to_vec(),
+                content_type: resource.content_type.clone(),
+                from_cache: true,
+                status: 200,
+                headers: response.headers,
+                timings,
+                overridden: false,
+            });
+        }
+        
+        if !response.is_success() {
+            return Err(NetworkError::HttpError(status));
+        }
+        
         let etag = response.headers.iter()
             .find(|(n, _)| n.eq_ignore_ascii_case("etag"))
             .map(|(_, v)| v.clone());
         
+        let last_modified = response.headers.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case("last-modified"))
+            .map(|(_, v)| v.clone());
+        
         let content_type = response.headers.iter()
             .find(|(n, _)| n.eq_ignore_ascii_case("content-type"))
             .map(|(_, v)| v.clone())
@@ -162,12 +452,9 @@ impl NetworkManager {
         
         let body = response.body;
         
-        // Determine cache TTL
-        let max_age = parse_max_age(&cache_control).unwrap_or(Duration::from_secs(300));
-        
-        // Store in cache if cacheable
+        // Store in cache if cacheable; other tabs waiting on it get it too
         if !cache_control.contains("no-store") && status == 200 {
-            self.cache.put(url, body.clone(), &content_type, etag, max_age);
+            load.complete(&content_type, body.clone(), CacheValidators { etag, last_modified }, max_age);
             log::debug!("Cached response for {} ({} bytes, TTL {:?})", url, body.len(), max_age);
         }
         
@@ -176,12 +463,26 @@ impl NetworkManager {
             content_type,
             from_cache: false,
             status,
+            headers: response.headers,
+            timings,
+            overridden: false,
         })
     }
     
+    /// Learn an HSTS policy from a response over a valid HTTPS connection
+    fn record_hsts(&mut self, url: &str, headers: &[(String, String)]) {
+        let Some(header) = headers.iter().find(|(n, _)| n.eq_ignore_ascii_case("strict-transport-security")) else { return };
+        let Ok(parsed) = fos_engine::url::Url::parse(url) else { return };
+        let Some(host) = parsed.host_str().filter(|_| parsed.scheme() == "https") else { return };
+        // A certificate the user let through proves nothing about the host
+        if !self.certificate_overrides.allows(host) {
+            self.hsts.record(host, &header.1);
+        }
+    }
+    
     /// Fetch HTML page (convenience method)
     pub fn fetch_html(&mut self, url: &str) -> Result<String, NetworkError> {
-        let result = self.fetch(url, None)?;
+        let result = self.fetch_as(url, None, ResourceType::Document, &[])?;
         String::from_utf8(result.body)
             .map_err(|e| NetworkError::InvalidEncoding(e.to_string()))
     }
@@ -196,21 +497,48 @@ impl NetworkManager {
         self.cache.stats()
     }
     
+    /// Responses in the HTTP cache, largest first
+    pub fn cached_resources(&self) -> Vec<CachedResource> {
+        let mut resources: Vec<CachedResource> = self.cache.entries().into_iter()
+            .map(|entry| CachedResource {
+                url: entry.url,
+                content_type: entry.content_type,
+                size: entry.size,
+                ttl: entry.ttl,
+                hits: entry.hits,
+            })
+            .collect();
+        resources.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.url.cmp(&b.url)));
+        resources
+    }
+    
+    /// Drop `url` from the cache, for every tab sharing it; false if it
+    /// was not cached
+    pub fn evict_cached(&mut self, url: &str) -> bool {
+        self.cache.invalidate(url)
+    }
+    
+    /// User agent sent with requests
+    pub fn set_user_agent(&mut self, user_agent: &str) {
+        self.user_agent = user_agent.to_string();
+    }
+    
     /// Clear the cache
     pub fn clear_cache(&mut self) {
         self.cache.clear();
     }
     
-    /// Clean up expired entries
+    /// Drop stale entries that cannot be revalidated
     pub fn cleanup(&mut self) {
         self.cache.cleanup();
     }
     
     /// Get network statistics
     pub fn stats(&self) -> NetworkStats {
+        let cache = self.cache.stats();
         NetworkStats {
-            cache_entries: self.cache.stats().entry_count,
-            cache_size_bytes: self.cache.stats().total_size,
+            cache_entries: cache.entry_count,
+            cache_size_bytes: cache.total_size,
             http2_connections: self.http2_pool.len(),
         }
     }
@@ -230,6 +558,19 @@ pub struct NetworkStats {
     pub http2_connections: usize,
 }
 
+/// Response held in the HTTP cache
+#[derive(Debug, Clone)]
+pub struct CachedResource {
+    pub url: String,
+    pub content_type: String,
+    /// Body size in bytes
+    pub size: usize,
+    /// Time left before the response goes stale
+    pub ttl: Duration,
+    /// Times the response was served from the cache
+    pub hits: u32,
+}
+
 /// Result of a fetch operation
 #[derive(Debug)]
 pub struct FetchResult {
@@ -241,6 +582,12 @@ pub struct FetchResult {
     pub from_cache: bool,
     /// HTTP status code
     pub status: u16,
+    /// Response headers
+    pub headers: Vec<(String, String)>,
+    /// Phase timings; `None` for cached and overridden responses
+    pub timings: Option<RequestTimings>,
+    /// Whether a DevTools override answered instead of the network
+    pub overridden: bool,
 }
 
 /// Network error
@@ -255,11 +602,17 @@ pub enum NetworkError {
     #[error("Mixed content blocked: {0}")]
     MixedContentBlocked(String),
     
+    #[error("Blocked by request rules: {0}")]
+    Blocked(String),
+    
     #[error("CORS blocked: {0}")]
     CorsBlocked(String),
     
     #[error("Invalid encoding: {0}")]
     InvalidEncoding(String),
+    
+    #[error("TLS error: {0}")]
+    Tls(TlsError),
 }
 
 /// Parse max-age from Cache-Control header
@@ -292,6 +645,141 @@ mod tests {
         assert!(!manager.is_cached("https://example.com"));
     }
     
+    #[test]
+    fn test_no_certificate_overrides_for_hsts_hosts() {
+        let mut manager = NetworkManager::new();
+        let sts = vec![("Strict-Transport-Security".to_string(), "max-age=600".to_string())];
+        manager.record_hsts("https://secure.example/", &sts);
+        manager.record_hsts("http://plain.example/", &sts);
+        assert!(manager.is_hsts_host("secure.example"));
+        
+        assert!(!manager.allow_invalid_certificate("secure.example", "ERR_CERT_DATE_INVALID"));
+        assert!(manager.allow_invalid_certificate("plain.example", "ERR_CERT_DATE_INVALID"));
+        
+        // Policies sent over an overridden connection are ignored
+        manager.record_hsts("https://plain.example/", &sts);
+        assert!(!manager.is_hsts_host("plain.example"));
+    }
+    
+    #[test]
+    fn test_response_override() {
+        let mut manager = NetworkManager::new();
+        manager.set_response_overrides(vec![
+            ResponseOverride::new("https://example.com/*", "<p>local</p>").with_status(203),
+        ]);
+        let result = manager.fetch("https://example.com/page", None).unwrap();
+        assert!(result.overridden);
+        assert_eq!(result.status, 203);
+        assert_eq!(result.body, b"<p>local</p>");
+        assert_eq!(result.content_type, "text/html");
+    }
+    
+    #[test]
+    fn test_interceptors() {
+        struct BlockAll;
+        impl RequestInterceptor for BlockAll {
+            fn intercept_request(&self, _request: &mut Request) -> fos_net::Interception {
+                fos_net::Interception::Block
+            }
+        }
+        
+        let mut manager = NetworkManager::new().with_interceptor(Arc::new(BlockAll));
+        let result = manager.fetch("https://tracker.example/pixel.gif", Some("https://example.com/"));
+        assert!(matches!(result, Err(NetworkError::Blocked(_))));
+        
+        // DevTools overrides still come first
+        manager.set_response_overrides(vec![ResponseOverride::new("https://tracker.example/*", "")]);
+        assert!(manager.fetch("https://tracker.example/pixel.gif", None).unwrap().overridden);
+    }
+    
+    #[test]
+    fn test_content_blocking() {
+        // Blocks what reaches it, recording the URL
+        #[derive(Default)]
+        struct Reached(std::sync::Mutex<Vec<String>>);
+        impl RequestInterceptor for Reached {
+            fn intercept_request(&self, request: &mut Request) -> fos_net::Interception {
+                self.0.lock().unwrap().push(request.url.as_str().to_string());
+                fos_net::Interception::Block
+            }
+        }
+        
+        let mut blocking = ContentBlocking::new();
+        blocking.set_blocked(ContentCategory::Scripts, true);
+        blocking.set_blocked(ContentCategory::Fonts, true);
+        let reached = Arc::new(Reached::default());
+        let mut manager = NetworkManager::new().with_interceptor(reached.clone());
+        manager.set_content_blocking(blocking.clone());
+        let page = Some("https://news.com/");
+        for url in ["https://cdn.net/app.js", "https://fonts.cdn.net/a.woff2", "https://news.com/app.js", "https://cdn.net/logo.png"] {
+            let _ = manager.fetch(url, page);
+        }
+        let _ = manager.fetch("https://cdn.net/app.js", None);
+        
+        // Only first-party scripts, other types and top-level loads go out
+        assert_eq!(*reached.0.lock().unwrap(), ["https://news.com/app.js", "https://cdn.net/logo.png", "https://cdn.net/app.js"]);
+        
+        // Until the site is unblocked
+        reached.0.lock().unwrap().clear();
+        blocking.unblock("https://news.com/", ContentCategory::Scripts);
+        manager.set_content_blocking(blocking);
+        let _ = manager.fetch("https://cdn.net/app.js", page);
+        let _ = manager.fetch("https://fonts.cdn.net/a.woff2", page);
+        assert_eq!(*reached.0.lock().unwrap(), ["https://cdn.net/app.js"]);
+    }
+    
+    #[test]
+    fn test_privacy_signals_follow_preferences() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let origin = format!("http://127.0.0.1:{}/", listener.local_addr().unwrap().port());
+        let server = std::thread::spawn(move || {
+            let mut gpc = Vec::new();
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut head = Vec::new();
+                let mut byte = [0u8];
+                while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap() == 1 {
+                    head.push(byte[0]);
+                }
+                gpc.push(String::from_utf8(head).unwrap().to_ascii_lowercase().contains("sec-gpc: 1"));
+                write!(stream, "HTTP/1.1 200 OK\r\nCache-Control: no-store\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+            }
+            gpc
+        });
+        
+        let signals = Arc::new(Mutex::new(PrivacySignals::new()));
+        let mut manager = NetworkManager::new();
+        manager.set_privacy_signals(signals.clone());
+        manager.fetch(&format!("{}a", origin), None).unwrap();
+        // Turned on in the preferences after the manager was set up
+        signals.lock().unwrap().global_privacy_control = true;
+        manager.fetch(&format!("{}b", origin), None).unwrap();
+        assert_eq!(server.join().unwrap(), [false, true]);
+    }
+    
+    #[test]
+    fn test_cross_tab_cache_shared_between_tabs() {
+        let mut first = NetworkManager::new();
+        let mut second = NetworkManager::new().with_cross_tab_cache(first.cross_tab_cache());
+        let url = "https://example.com/shared.css";
+        
+        // What one tab loaded, the other gets without a request
+        let CrossTabLookup::Load(load) = first.cross_tab_cache().lookup(url) else { panic!() };
+        let validators = CacheValidators { etag: Some("\"a\"".into()), last_modified: None };
+        load.complete("text/css", b"p{}".to_vec(), validators, Duration::from_secs(60));
+        let result = second.fetch(url, None).unwrap();
+        assert!(result.from_cache);
+        assert_eq!(result.body, b"p{}");
+        assert_eq!(first.stats().cache_size_bytes, 3);
+        
+        // Clearing one tab's cache clears the other's
+        first.clear_cache();
+        assert!(!second.is_cached(url));
+    }
+    
     #[test]
     fn test_network_stats() {
         let manager = NetworkManager::new();
@@ -150,6 +150,11 @@ impl NetworkInfoManager {
         }
     }
     
+    /// Set the user's data saver preference, reported as saveData
+    pub fn set_save_data(&mut self, enabled: bool) {
+        self.info.save_data = enabled;
+    }
+    
     /// Update from system
     pub fn update(&mut self) {
         self.info.update();
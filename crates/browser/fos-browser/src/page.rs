@@ -5,6 +5,8 @@
 use std::sync::{Arc, Mutex};
 use fos_dom::Document;
 use crate::js_runtime::PageJsRuntime;
+#[cfg(feature = "extensions")]
+use crate::extensions::{ExtensionManager, RunAt};
 
 /// A loaded web page
 pub struct Page {
@@ -144,6 +146,32 @@ impl Page {
         Ok(())
     }
     
+    /// Inject the content scripts due at one point of the page's load,
+    /// each extension's scripts in its own isolated world; returns how
+    /// many ran
+    #[cfg(feature = "extensions")]
+    pub fn inject_content_scripts(&mut self, extensions: &ExtensionManager, run_at: RunAt) -> usize {
+        if !self.js_initialized {
+            return 0;
+        }
+        
+        let Some(ref mut js_runtime) = self.js_runtime else {
+            return 0;
+        };
+        
+        let scripts = extensions.scripts_to_inject(&self.url, run_at);
+        for script in &scripts {
+            if let Err(e) = js_runtime.execute_in_world(&script.extension_id, &script.source, &script.url) {
+                log::warn!("Failed to create isolated world for {}: {}", script.extension_id, e);
+            }
+        }
+        
+        if !scripts.is_empty() {
+            log::info!("Injected {} content scripts ({:?}) into {}", scripts.len(), run_at, self.url);
+        }
+        scripts.len()
+    }
+    
     /// Process JavaScript timers (call periodically)
     pub fn process_timers(&mut self) -> Result<(), String> {
         let Some(ref js_runtime) = self.js_runtime else {
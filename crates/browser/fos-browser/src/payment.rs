@@ -0,0 +1,1015 @@
+//! Payment Request API
+//!
+//! `PaymentRequest` / `PaymentResponse` for merchants. The browser shows a
+//! payment sheet listing the instruments of registered [`PaymentHandler`]s;
+//! the handler chosen by the user performs the actual payment.
+//!
+//! `hasEnrolledInstrument` is rate limited per origin so sites cannot probe
+//! which payment methods a user has set up.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::ui::font;
+
+/// How long an origin is bound to the method set it first queried
+const ENROLLED_QUERY_WINDOW: Duration = Duration::from_secs(30 * 60);
+
+/// Payment error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentError {
+    /// No registered handler supports the requested methods
+    NotSupported,
+    /// Invalid details (amounts, currency, duplicate ids)
+    InvalidData(String),
+    /// Request is not in the right state (already shown, closed, ...)
+    InvalidState,
+    /// `show()` without user activation, or `hasEnrolledInstrument` over quota
+    NotAllowed,
+    /// User dismissed the sheet or the merchant aborted
+    Aborted,
+    /// The payment handler failed
+    HandlerFailed(String),
+}
+
+impl std::fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotSupported => write!(f, "Payment method not supported"),
+            Self::InvalidData(msg) => write!(f, "Invalid payment details: {}", msg),
+            Self::InvalidState => write!(f, "Payment request in invalid state"),
+            Self::NotAllowed => write!(f, "Payment request not allowed"),
+            Self::Aborted => write!(f, "Payment request aborted"),
+            Self::HandlerFailed(msg) => write!(f, "Payment failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for PaymentError {}
+
+/// Method identifier with its method-specific data (JSON)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentMethodData {
+    pub supported_methods: String,
+    pub data: Option<String>,
+}
+
+impl PaymentMethodData {
+    pub fn new(method: &str) -> Self {
+        Self { supported_methods: method.to_string(), data: None }
+    }
+}
+
+/// Monetary amount
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentCurrencyAmount {
+    /// ISO 4217 currency code
+    pub currency: String,
+    /// Decimal value ("10.00", "-2.5")
+    pub value: String,
+}
+
+impl PaymentCurrencyAmount {
+    pub fn new(currency: &str, value: &str) -> Self {
+        Self { currency: currency.to_string(), value: value.to_string() }
+    }
+
+    fn validate(&self) -> Result<(), PaymentError> {
+        if self.currency.len() != 3 || !self.currency.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(PaymentError::InvalidData(format!("invalid currency code '{}'", self.currency)));
+        }
+        let digits = self.value.strip_prefix('-').unwrap_or(&self.value);
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (digits, None),
+        };
+        let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        if !is_digits(whole) || fraction.is_some_and(|f| !is_digits(f)) {
+            return Err(PaymentError::InvalidData(format!("invalid amount '{}'", self.value)));
+        }
+        Ok(())
+    }
+
+    fn is_negative(&self) -> bool {
+        self.value.starts_with('-') && self.value[1..].chars().any(|c| c.is_ascii_digit() && c != '0')
+    }
+
+    /// Display form ("USD 10.00")
+    pub fn display(&self) -> String {
+        format!("{} {}", self.currency.to_ascii_uppercase(), self.value)
+    }
+}
+
+/// Line item
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentItem {
+    pub label: String,
+    pub amount: PaymentCurrencyAmount,
+    /// Amount may still change
+    pub pending: bool,
+}
+
+impl PaymentItem {
+    pub fn new(label: &str, currency: &str, value: &str) -> Self {
+        Self { label: label.to_string(), amount: PaymentCurrencyAmount::new(currency, value), pending: false }
+    }
+}
+
+/// Shipping option offered by the merchant
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentShippingOption {
+    pub id: String,
+    pub label: String,
+    pub amount: PaymentCurrencyAmount,
+    pub selected: bool,
+}
+
+/// Details passed to the constructor
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentDetailsInit {
+    /// Merchant transaction id; generated when empty
+    pub id: String,
+    pub total: PaymentItem,
+    pub display_items: Vec<PaymentItem>,
+    pub shipping_options: Vec<PaymentShippingOption>,
+}
+
+/// Details passed to `updateWith` after a change event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PaymentDetailsUpdate {
+    pub total: Option<PaymentItem>,
+    pub display_items: Option<Vec<PaymentItem>>,
+    pub shipping_options: Option<Vec<PaymentShippingOption>>,
+    /// Shown to the user, e.g. "Cannot ship to this address"
+    pub error: Option<String>,
+}
+
+/// Shipping type label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaymentShippingType {
+    #[default]
+    Shipping,
+    Delivery,
+    Pickup,
+}
+
+/// What the merchant asks from the payer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PaymentOptions {
+    pub request_payer_name: bool,
+    pub request_payer_email: bool,
+    pub request_payer_phone: bool,
+    pub request_shipping: bool,
+    pub shipping_type: PaymentShippingType,
+}
+
+/// Postal address
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PaymentAddress {
+    pub country: String,
+    pub address_line: Vec<String>,
+    pub region: String,
+    pub city: String,
+    pub postal_code: String,
+    pub recipient: String,
+    pub phone: String,
+}
+
+/// Contact details the user filled in (or autofill provided)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PayerDetails {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub shipping_address: Option<PaymentAddress>,
+}
+
+/// Instrument a handler can pay with (a card, an account)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentInstrument {
+    pub id: String,
+    pub label: String,
+}
+
+/// What a handler receives when the user confirms
+#[derive(Debug, Clone)]
+pub struct PaymentHandlerRequest {
+    pub top_origin: String,
+    pub payment_request_id: String,
+    pub method_data: PaymentMethodData,
+    pub total: PaymentCurrencyAmount,
+    pub instrument_id: String,
+}
+
+/// Provider of a payment method, implemented by embedders
+pub trait PaymentHandler: Send + std::fmt::Debug {
+    /// Method identifier ("basic-card", "https://pay.example.com")
+    fn method(&self) -> &str;
+
+    /// Whether the handler accepts this method data
+    fn can_make_payment(&self, _data: &PaymentMethodData) -> bool { true }
+
+    /// Instruments to list in the payment sheet
+    fn instruments(&self) -> Vec<PaymentInstrument>;
+
+    /// Perform the payment; returns method-specific details (JSON)
+    fn pay(&mut self, request: &PaymentHandlerRequest) -> Result<String, PaymentError>;
+
+    /// The request was aborted while the handler was selected
+    fn abort(&mut self) {}
+}
+
+/// Merchant's result for `PaymentResponse.complete()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaymentComplete {
+    #[default]
+    Unknown,
+    Success,
+    Fail,
+}
+
+/// Response resolved from `show()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentResponse {
+    pub request_id: String,
+    pub method_name: String,
+    /// Method-specific details from the handler (JSON)
+    pub details: String,
+    pub shipping_address: Option<PaymentAddress>,
+    pub shipping_option: Option<String>,
+    pub payer_name: Option<String>,
+    pub payer_email: Option<String>,
+    pub payer_phone: Option<String>,
+}
+
+/// Request lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentRequestState {
+    Created,
+    Interactive,
+    /// Response delivered, waiting for `complete()`
+    Responded,
+    Closed,
+}
+
+/// Events fired at the merchant's `PaymentRequest`
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaymentRequestEvent {
+    ShippingAddressChange(u64),
+    ShippingOptionChange(u64),
+    PaymentMethodChange(u64),
+    /// `show()` resolved
+    Response(u64),
+    /// `show()` rejected
+    Rejected(u64, PaymentError),
+}
+
+/// A `PaymentRequest`
+#[derive(Debug, Clone)]
+pub struct PaymentRequest {
+    pub id: u64,
+    pub origin: String,
+    pub method_data: Vec<PaymentMethodData>,
+    pub details: PaymentDetailsInit,
+    pub options: PaymentOptions,
+    pub state: PaymentRequestState,
+    pub shipping_address: Option<PaymentAddress>,
+    pub shipping_option: Option<String>,
+    response: Option<PaymentResponse>,
+    complete: Option<PaymentComplete>,
+}
+
+impl PaymentRequest {
+    fn validate(method_data: &[PaymentMethodData], details: &PaymentDetailsInit) -> Result<(), PaymentError> {
+        if method_data.is_empty() || method_data.iter().any(|m| m.supported_methods.is_empty()) {
+            return Err(PaymentError::InvalidData("at least one payment method is required".into()));
+        }
+        details.total.amount.validate()?;
+        if details.total.amount.is_negative() {
+            return Err(PaymentError::InvalidData("total must not be negative".into()));
+        }
+        for item in &details.display_items {
+            item.amount.validate()?;
+        }
+        for (i, option) in details.shipping_options.iter().enumerate() {
+            option.amount.validate()?;
+            if details.shipping_options[..i].iter().any(|o| o.id == option.id) {
+                return Err(PaymentError::InvalidData(format!("duplicate shipping option '{}'", option.id)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Selected shipping option: the last one marked selected
+    fn default_shipping_option(options: &[PaymentShippingOption]) -> Option<String> {
+        options.iter().rev().find(|o| o.selected).map(|o| o.id.clone())
+    }
+
+    /// Result passed to `complete()`, once called
+    pub fn completion(&self) -> Option<PaymentComplete> {
+        self.complete
+    }
+}
+
+/// Sheet state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentSheetState {
+    /// Waiting for the user
+    Ready,
+    /// Waiting for the merchant's `updateWith`
+    Updating,
+    /// Handler running
+    Processing,
+    /// Error to show next to the confirm button
+    Error(String),
+}
+
+/// Sheet rows, used for keyboard focus and rendering
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentSheetRow {
+    Instrument(usize),
+    ShippingOption(usize),
+    Confirm,
+    Cancel,
+}
+
+/// Browser payment sheet for the interactive request
+#[derive(Debug, Clone)]
+pub struct PaymentSheet {
+    pub request_id: u64,
+    /// Origin shown to the user
+    pub origin: String,
+    pub total: PaymentItem,
+    pub display_items: Vec<PaymentItem>,
+    pub shipping_options: Vec<PaymentShippingOption>,
+    /// (handler index, method data index, instrument)
+    pub instruments: Vec<(usize, usize, PaymentInstrument)>,
+    pub selected_instrument: Option<usize>,
+    pub selected_shipping: Option<String>,
+    pub payer: PayerDetails,
+    pub options: PaymentOptions,
+    pub state: PaymentSheetState,
+    pub focused: PaymentSheetRow,
+}
+
+/// Sheet colors (ARGB)
+mod colors {
+    pub const SCRIM: u32 = 0xC0000000;
+    pub const BG: u32 = 0xFF1A3A3A;
+    pub const TEXT: u32 = 0xFFE0E0E0;
+    pub const TEXT_DIM: u32 = 0xFF80A0A0;
+    pub const ACCENT: u32 = 0xFF40C0C0;
+    pub const FOCUS: u32 = 0xFF2A5A5A;
+    pub const ERROR: u32 = 0xFFE04040;
+}
+
+/// Sheet width and row height in pixels
+const SHEET_WIDTH: usize = 360;
+const ROW_HEIGHT: usize = 14;
+
+impl PaymentSheet {
+    /// Rows in display order
+    pub fn rows(&self) -> Vec<PaymentSheetRow> {
+        let mut rows: Vec<_> = (0..self.instruments.len()).map(PaymentSheetRow::Instrument).collect();
+        if self.options.request_shipping {
+            rows.extend((0..self.shipping_options.len()).map(PaymentSheetRow::ShippingOption));
+        }
+        rows.push(PaymentSheetRow::Confirm);
+        rows.push(PaymentSheetRow::Cancel);
+        rows
+    }
+
+    /// Move keyboard focus by `delta` rows
+    pub fn move_focus(&mut self, delta: i32) {
+        let rows = self.rows();
+        let current = rows.iter().position(|r| *r == self.focused).unwrap_or(0) as i32;
+        let next = (current + delta).rem_euclid(rows.len() as i32) as usize;
+        self.focused = rows[next];
+    }
+
+    /// Missing information that blocks confirming
+    pub fn missing_field(&self) -> Option<&'static str> {
+        if self.selected_instrument.is_none() {
+            return Some("payment method");
+        }
+        if self.options.request_shipping {
+            if self.payer.shipping_address.is_none() {
+                return Some("shipping address");
+            }
+            if self.selected_shipping.is_none() {
+                return Some("shipping option");
+            }
+        }
+        if self.options.request_payer_name && self.payer.name.is_none() {
+            return Some("name");
+        }
+        if self.options.request_payer_email && self.payer.email.is_none() {
+            return Some("email");
+        }
+        if self.options.request_payer_phone && self.payer.phone.is_none() {
+            return Some("phone");
+        }
+        None
+    }
+
+    /// Render centered over the content area
+    pub fn render(&self, buffer: &mut [u32], buffer_width: usize, buffer_height: usize) {
+        // Dim the page
+        for pixel in buffer.iter_mut().take(buffer_width * buffer_height) {
+            *pixel = blend(*pixel, colors::SCRIM);
+        }
+
+        let mut lines: Vec<(String, u32, Option<PaymentSheetRow>)> = vec![
+            (format!("Pay {}", self.origin), colors::ACCENT, None),
+            (String::new(), colors::TEXT, None),
+        ];
+        for item in &self.display_items {
+            lines.push((format!("{}  {}", item.label, item.amount.display()), colors::TEXT_DIM, None));
+        }
+        let pending = if self.total.pending { " (pending)" } else { "" };
+        lines.push((format!("{}  {}{}", self.total.label, self.total.amount.display(), pending), colors::TEXT, None));
+        lines.push((String::new(), colors::TEXT, None));
+
+        for (i, (_, _, instrument)) in self.instruments.iter().enumerate() {
+            let mark = if self.selected_instrument == Some(i) { '*' } else { ' ' };
+            lines.push((format!("{} {}", mark, instrument.label), colors::TEXT, Some(PaymentSheetRow::Instrument(i))));
+        }
+        if self.options.request_shipping {
+            lines.push((String::new(), colors::TEXT, None));
+            for (i, option) in self.shipping_options.iter().enumerate() {
+                let mark = if self.selected_shipping.as_deref() == Some(option.id.as_str()) { '*' } else { ' ' };
+                let text = format!("{} {}  {}", mark, option.label, option.amount.display());
+                lines.push((text, colors::TEXT, Some(PaymentSheetRow::ShippingOption(i))));
+            }
+        }
+        lines.push((String::new(), colors::TEXT, None));
+        let confirm = match self.state {
+            PaymentSheetState::Processing => "Processing...",
+            PaymentSheetState::Updating => "Updating...",
+            _ => "Pay",
+        };
+        lines.push((confirm.to_string(), colors::ACCENT, Some(PaymentSheetRow::Confirm)));
+        lines.push(("Cancel".to_string(), colors::TEXT_DIM, Some(PaymentSheetRow::Cancel)));
+        if let PaymentSheetState::Error(message) = &self.state {
+            lines.push((message.clone(), colors::ERROR, None));
+        }
+
+        let width = SHEET_WIDTH.min(buffer_width);
+        let height = (lines.len() * ROW_HEIGHT + 16).min(buffer_height);
+        let x = (buffer_width - width) / 2;
+        let y = (buffer_height - height) / 2;
+        fill_rect(buffer, buffer_width, buffer_height, (x, y, width, height), colors::BG);
+
+        for (i, (text, color, row)) in lines.iter().enumerate() {
+            let row_y = y + 8 + i * ROW_HEIGHT;
+            if *row == Some(self.focused) {
+                fill_rect(buffer, buffer_width, buffer_height, (x + 4, row_y - 3, width - 8, ROW_HEIGHT), colors::FOCUS);
+            }
+            let max_chars = (width - 16) / 7;
+            let text: String = text.chars().take(max_chars).collect();
+            font::draw_text(buffer, buffer_width, buffer_height, x + 8, row_y, &text, *color);
+        }
+    }
+}
+
+/// Fill `(x, y, width, height)`
+fn fill_rect(buffer: &mut [u32], buffer_width: usize, buffer_height: usize, rect: (usize, usize, usize, usize), color: u32) {
+    let (x, y, width, height) = rect;
+    for py in y..(y + height).min(buffer_height) {
+        for px in x..(x + width).min(buffer_width) {
+            buffer[py * buffer_width + px] = color;
+        }
+    }
+}
+
+/// Alpha-blend an ARGB color over an opaque pixel
+fn blend(dst: u32, src: u32) -> u32 {
+    let alpha = src >> 24;
+    let channel = |shift: u32| {
+        let s = (src >> shift) & 0xFF;
+        let d = (dst >> shift) & 0xFF;
+        ((s * alpha + d * (255 - alpha)) / 255) << shift
+    };
+    0xFF000000 | channel(16) | channel(8) | channel(0)
+}
+
+/// Payment manager: handlers, requests and the sheet
+#[derive(Debug, Default)]
+pub struct PaymentManager {
+    handlers: Vec<Box<dyn PaymentHandler>>,
+    requests: HashMap<u64, PaymentRequest>,
+    sheet: Option<PaymentSheet>,
+    /// Method set each origin queried `hasEnrolledInstrument` with, and when
+    enrolled_queries: HashMap<String, (Vec<String>, Instant)>,
+    /// Private browsing: never reveal enrolled instruments
+    pub private_mode: bool,
+    events: Vec<PaymentRequestEvent>,
+    next_id: u64,
+}
+
+impl PaymentManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a payment handler provided by the embedder
+    pub fn register_handler(&mut self, handler: Box<dyn PaymentHandler>) {
+        self.handlers.retain(|h| h.method() != handler.method());
+        self.handlers.push(handler);
+    }
+
+    /// `new PaymentRequest(methodData, details, options)`
+    pub fn create_request(
+        &mut self,
+        origin: &str,
+        method_data: Vec<PaymentMethodData>,
+        mut details: PaymentDetailsInit,
+        options: PaymentOptions,
+    ) -> Result<u64, PaymentError> {
+        PaymentRequest::validate(&method_data, &details)?;
+        self.next_id += 1;
+        let id = self.next_id;
+        if details.id.is_empty() {
+            details.id = format!("payment-{}", id);
+        }
+        let shipping_option = if options.request_shipping {
+            PaymentRequest::default_shipping_option(&details.shipping_options)
+        } else {
+            None
+        };
+        self.requests.insert(id, PaymentRequest {
+            id,
+            origin: origin.to_string(),
+            method_data,
+            details,
+            options,
+            state: PaymentRequestState::Created,
+            shipping_address: None,
+            shipping_option,
+            response: None,
+            complete: None,
+        });
+        Ok(id)
+    }
+
+    pub fn request(&self, id: u64) -> Option<&PaymentRequest> {
+        self.requests.get(&id)
+    }
+
+    /// Handler indices supporting a method data entry
+    fn handlers_for(&self, data: &PaymentMethodData) -> impl Iterator<Item = usize> + '_ {
+        let method = data.supported_methods.clone();
+        let data = data.clone();
+        self.handlers.iter().enumerate()
+            .filter(move |(_, h)| h.method() == method && h.can_make_payment(&data))
+            .map(|(i, _)| i)
+    }
+
+    /// `canMakePayment()`: whether any requested method is supported. Never
+    /// reveals whether the user has instruments set up.
+    pub fn can_make_payment(&self, id: u64) -> bool {
+        self.requests.get(&id).is_some_and(|request| {
+            request.method_data.iter().any(|data| self.handlers_for(data).next().is_some())
+        })
+    }
+
+    /// `hasEnrolledInstrument()`. An origin may only query one method set
+    /// per window; other sets are rejected until it expires.
+    pub fn has_enrolled_instrument(&mut self, id: u64) -> Result<bool, PaymentError> {
+        let request = self.requests.get(&id).ok_or(PaymentError::InvalidState)?;
+        let mut methods: Vec<String> = request.method_data.iter().map(|m| m.supported_methods.clone()).collect();
+        methods.sort();
+        methods.dedup();
+
+        let now = Instant::now();
+        match self.enrolled_queries.get(&request.origin) {
+            Some((queried, at)) if now.duration_since(*at) < ENROLLED_QUERY_WINDOW => {
+                if *queried != methods {
+                    return Err(PaymentError::NotAllowed);
+                }
+            }
+            _ => {
+                self.enrolled_queries.insert(request.origin.clone(), (methods, now));
+            }
+        }
+
+        let supported = self.can_make_payment(id);
+        if self.private_mode {
+            return Ok(supported);
+        }
+        let request = &self.requests[&id];
+        Ok(request.method_data.iter().any(|data| {
+            self.handlers_for(data).any(|h| !self.handlers[h].instruments().is_empty())
+        }))
+    }
+
+    /// `show()`: open the payment sheet. Requires user activation and only
+    /// one sheet may be open at a time.
+    pub fn show(&mut self, id: u64, user_activation: bool) -> Result<(), PaymentError> {
+        let request = self.requests.get(&id).ok_or(PaymentError::InvalidState)?;
+        if request.state != PaymentRequestState::Created {
+            return Err(PaymentError::InvalidState);
+        }
+        let result = if !user_activation {
+            Err(PaymentError::NotAllowed)
+        } else if self.sheet.is_some() {
+            Err(PaymentError::Aborted)
+        } else {
+            let instruments: Vec<_> = request.method_data.iter().enumerate()
+                .flat_map(|(data_index, data)| self.handlers_for(data).map(move |h| (h, data_index)))
+                .flat_map(|(h, data_index)| {
+                    self.handlers[h].instruments().into_iter().map(move |instrument| (h, data_index, instrument))
+                })
+                .collect();
+            if instruments.is_empty() {
+                Err(PaymentError::NotSupported)
+            } else {
+                Ok(instruments)
+            }
+        };
+
+        let request = self.requests.get_mut(&id).ok_or(PaymentError::InvalidState)?;
+        let instruments = match result {
+            Ok(instruments) => instruments,
+            Err(e) => {
+                request.state = PaymentRequestState::Closed;
+                return Err(e);
+            }
+        };
+        request.state = PaymentRequestState::Interactive;
+        self.sheet = Some(PaymentSheet {
+            request_id: id,
+            origin: request.origin.clone(),
+            total: request.details.total.clone(),
+            display_items: request.details.display_items.clone(),
+            shipping_options: request.details.shipping_options.clone(),
+            selected_instrument: (instruments.len() == 1).then_some(0),
+            instruments,
+            selected_shipping: request.shipping_option.clone(),
+            payer: PayerDetails::default(),
+            options: request.options.clone(),
+            state: PaymentSheetState::Ready,
+            focused: PaymentSheetRow::Confirm,
+        });
+        Ok(())
+    }
+
+    /// Open sheet, for rendering
+    pub fn sheet(&self) -> Option<&PaymentSheet> {
+        self.sheet.as_ref()
+    }
+
+    /// Fill payer details (from user input or autofill)
+    pub fn set_payer_details(&mut self, payer: PayerDetails) {
+        let Some(sheet) = &mut self.sheet else { return };
+        let address_changed = payer.shipping_address != sheet.payer.shipping_address && payer.shipping_address.is_some();
+        sheet.payer = payer;
+        if address_changed && sheet.options.request_shipping {
+            let id = sheet.request_id;
+            sheet.state = PaymentSheetState::Updating;
+            if let Some(request) = self.requests.get_mut(&id) {
+                request.shipping_address = sheet.payer.shipping_address.clone();
+            }
+            self.events.push(PaymentRequestEvent::ShippingAddressChange(id));
+        }
+    }
+
+    /// User picked an instrument
+    pub fn select_instrument(&mut self, index: usize) {
+        let Some(sheet) = &mut self.sheet else { return };
+        if index >= sheet.instruments.len() || sheet.selected_instrument == Some(index) {
+            return;
+        }
+        let method_changed = sheet.selected_instrument
+            .is_some_and(|old| sheet.instruments[old].0 != sheet.instruments[index].0);
+        sheet.selected_instrument = Some(index);
+        sheet.focused = PaymentSheetRow::Instrument(index);
+        if method_changed {
+            self.events.push(PaymentRequestEvent::PaymentMethodChange(sheet.request_id));
+        }
+    }
+
+    /// User picked a shipping option
+    pub fn select_shipping_option(&mut self, index: usize) {
+        let Some(sheet) = &mut self.sheet else { return };
+        let Some(option) = sheet.shipping_options.get(index) else { return };
+        if sheet.selected_shipping.as_deref() == Some(option.id.as_str()) {
+            return;
+        }
+        sheet.selected_shipping = Some(option.id.clone());
+        sheet.focused = PaymentSheetRow::ShippingOption(index);
+        sheet.state = PaymentSheetState::Updating;
+        let id = sheet.request_id;
+        if let Some(request) = self.requests.get_mut(&id) {
+            request.shipping_option = sheet.selected_shipping.clone();
+        }
+        self.events.push(PaymentRequestEvent::ShippingOptionChange(id));
+    }
+
+    /// Activate the focused row (Enter key)
+    pub fn activate_focused(&mut self) {
+        let Some(sheet) = &self.sheet else { return };
+        match sheet.focused {
+            PaymentSheetRow::Instrument(i) => self.select_instrument(i),
+            PaymentSheetRow::ShippingOption(i) => self.select_shipping_option(i),
+            PaymentSheetRow::Confirm => { let _ = self.confirm(); }
+            PaymentSheetRow::Cancel => self.cancel(),
+        }
+    }
+
+    /// Move keyboard focus in the sheet
+    pub fn move_focus(&mut self, delta: i32) {
+        if let Some(sheet) = &mut self.sheet {
+            sheet.move_focus(delta);
+        }
+    }
+
+    /// `event.updateWith(details)` from a change event
+    pub fn update_with(&mut self, id: u64, update: PaymentDetailsUpdate) -> Result<(), PaymentError> {
+        let request = self.requests.get_mut(&id).ok_or(PaymentError::InvalidState)?;
+        if request.state != PaymentRequestState::Interactive {
+            return Err(PaymentError::InvalidState);
+        }
+        if let Some(total) = &update.total {
+            total.amount.validate()?;
+            if total.amount.is_negative() {
+                return Err(PaymentError::InvalidData("total must not be negative".into()));
+            }
+        }
+        for item in update.display_items.iter().flatten() {
+            item.amount.validate()?;
+        }
+        for option in update.shipping_options.iter().flatten() {
+            option.amount.validate()?;
+        }
+
+        if let Some(total) = update.total {
+            request.details.total = total;
+        }
+        if let Some(items) = update.display_items {
+            request.details.display_items = items;
+        }
+        if let Some(options) = update.shipping_options {
+            request.shipping_option = PaymentRequest::default_shipping_option(&options);
+            request.details.shipping_options = options;
+        }
+
+        if let Some(sheet) = self.sheet.as_mut().filter(|s| s.request_id == id) {
+            sheet.total = request.details.total.clone();
+            sheet.display_items = request.details.display_items.clone();
+            sheet.shipping_options = request.details.shipping_options.clone();
+            sheet.selected_shipping = request.shipping_option.clone();
+            sheet.state = match update.error {
+                Some(message) => PaymentSheetState::Error(message),
+                None => PaymentSheetState::Ready,
+            };
+        }
+        Ok(())
+    }
+
+    /// User confirmed: run the selected handler and resolve `show()`
+    pub fn confirm(&mut self) -> Result<u64, PaymentError> {
+        let sheet = self.sheet.as_mut().ok_or(PaymentError::InvalidState)?;
+        if sheet.state == PaymentSheetState::Updating || sheet.state == PaymentSheetState::Processing {
+            return Err(PaymentError::InvalidState);
+        }
+        if let Some(field) = sheet.missing_field() {
+            sheet.state = PaymentSheetState::Error(format!("Missing {}", field));
+            return Err(PaymentError::InvalidState);
+        }
+        let id = sheet.request_id;
+        let request = self.requests.get(&id).ok_or(PaymentError::InvalidState)?;
+        let (handler, data_index, instrument) = sheet.instruments[sheet.selected_instrument.unwrap_or(0)].clone();
+
+        sheet.state = PaymentSheetState::Processing;
+        let handler_request = PaymentHandlerRequest {
+            top_origin: request.origin.clone(),
+            payment_request_id: request.details.id.clone(),
+            method_data: request.method_data[data_index].clone(),
+            total: request.details.total.amount.clone(),
+            instrument_id: instrument.id,
+        };
+        let details = match self.handlers[handler].pay(&handler_request) {
+            Ok(details) => details,
+            Err(e) => {
+                // Let the user pick another instrument
+                sheet.state = PaymentSheetState::Error(e.to_string());
+                return Err(e);
+            }
+        };
+
+        let payer = sheet.payer.clone();
+        let options = sheet.options.clone();
+        self.sheet = None;
+        let request = self.requests.get_mut(&id).ok_or(PaymentError::InvalidState)?;
+        request.state = PaymentRequestState::Responded;
+        request.response = Some(PaymentResponse {
+            request_id: request.details.id.clone(),
+            method_name: handler_request.method_data.supported_methods,
+            details,
+            shipping_address: options.request_shipping.then(|| payer.shipping_address.clone()).flatten(),
+            shipping_option: options.request_shipping.then(|| request.shipping_option.clone()).flatten(),
+            payer_name: payer.name.filter(|_| options.request_payer_name),
+            payer_email: payer.email.filter(|_| options.request_payer_email),
+            payer_phone: payer.phone.filter(|_| options.request_payer_phone),
+        });
+        self.events.push(PaymentRequestEvent::Response(id));
+        Ok(id)
+    }
+
+    /// User dismissed the sheet
+    pub fn cancel(&mut self) {
+        if let Some(sheet) = self.sheet.take() {
+            self.close(sheet.request_id, &sheet);
+        }
+    }
+
+    /// `abort()` from the merchant
+    pub fn abort(&mut self, id: u64) -> Result<(), PaymentError> {
+        let request = self.requests.get(&id).ok_or(PaymentError::InvalidState)?;
+        if request.state != PaymentRequestState::Interactive {
+            return Err(PaymentError::InvalidState);
+        }
+        if let Some(sheet) = self.sheet.take_if(|s| s.request_id == id) {
+            self.close(id, &sheet);
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, id: u64, sheet: &PaymentSheet) {
+        if let Some(&(handler, _, _)) = sheet.selected_instrument.and_then(|i| sheet.instruments.get(i)) {
+            self.handlers[handler].abort();
+        }
+        if let Some(request) = self.requests.get_mut(&id) {
+            request.state = PaymentRequestState::Closed;
+        }
+        self.events.push(PaymentRequestEvent::Rejected(id, PaymentError::Aborted));
+    }
+
+    /// Response for a resolved `show()`
+    pub fn response(&self, id: u64) -> Option<&PaymentResponse> {
+        self.requests.get(&id)?.response.as_ref()
+    }
+
+    /// `response.complete(result)`: closes the request
+    pub fn complete(&mut self, id: u64, result: PaymentComplete) -> Result<(), PaymentError> {
+        let request = self.requests.get_mut(&id).ok_or(PaymentError::InvalidState)?;
+        if request.state != PaymentRequestState::Responded {
+            return Err(PaymentError::InvalidState);
+        }
+        request.state = PaymentRequestState::Closed;
+        request.complete = Some(result);
+        Ok(())
+    }
+
+    /// Forget a request once the page dropped it
+    pub fn remove_request(&mut self, id: u64) {
+        self.requests.remove(&id);
+        if self.sheet.as_ref().is_some_and(|s| s.request_id == id) {
+            self.sheet = None;
+        }
+    }
+
+    /// Take pending events
+    pub fn take_events(&mut self) -> Vec<PaymentRequestEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct TestHandler { paid: Vec<String>, enrolled: bool }
+
+    impl PaymentHandler for TestHandler {
+        fn method(&self) -> &str { "https://pay.example" }
+
+        fn instruments(&self) -> Vec<PaymentInstrument> {
+            if self.enrolled {
+                vec![PaymentInstrument { id: "card-1".into(), label: "Visa ****1111".into() }]
+            } else {
+                Vec::new()
+            }
+        }
+
+        fn pay(&mut self, request: &PaymentHandlerRequest) -> Result<String, PaymentError> {
+            self.paid.push(request.total.value.clone());
+            Ok(format!("{{\"token\":\"{}\"}}", request.instrument_id))
+        }
+    }
+
+    fn details() -> PaymentDetailsInit {
+        PaymentDetailsInit {
+            id: String::new(),
+            total: PaymentItem::new("Total", "USD", "10.00"),
+            display_items: vec![PaymentItem::new("Book", "USD", "10.00")],
+            shipping_options: vec![
+                PaymentShippingOption { id: "std".into(), label: "Standard".into(), amount: PaymentCurrencyAmount::new("USD", "0.00"), selected: true },
+                PaymentShippingOption { id: "fast".into(), label: "Express".into(), amount: PaymentCurrencyAmount::new("USD", "5.00"), selected: false },
+            ],
+        }
+    }
+
+    fn manager(enrolled: bool) -> PaymentManager {
+        let mut mgr = PaymentManager::new();
+        mgr.register_handler(Box::new(TestHandler { paid: Vec::new(), enrolled }));
+        mgr
+    }
+
+    #[test]
+    fn test_validation() {
+        let mut mgr = manager(true);
+        let methods = vec![PaymentMethodData::new("https://pay.example")];
+        let mut bad = details();
+        bad.total = PaymentItem::new("Total", "USD", "-1.00");
+        assert!(mgr.create_request("https://shop.example", methods.clone(), bad, PaymentOptions::default()).is_err());
+        let mut bad = details();
+        bad.total = PaymentItem::new("Total", "US", "1");
+        assert!(mgr.create_request("https://shop.example", methods.clone(), bad, PaymentOptions::default()).is_err());
+        assert!(mgr.create_request("https://shop.example", Vec::new(), details(), PaymentOptions::default()).is_err());
+        assert!(mgr.create_request("https://shop.example", methods, details(), PaymentOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_payment_flow_with_shipping() {
+        let mut mgr = manager(true);
+        let options = PaymentOptions { request_shipping: true, request_payer_email: true, ..Default::default() };
+        let methods = vec![PaymentMethodData::new("https://pay.example")];
+        let id = mgr.create_request("https://shop.example", methods.clone(), details(), options.clone()).unwrap();
+        assert!(mgr.can_make_payment(id));
+
+        // Without user activation the request is rejected and closed
+        assert_eq!(mgr.show(id, false), Err(PaymentError::NotAllowed));
+        assert_eq!(mgr.show(id, true), Err(PaymentError::InvalidState));
+        let id = mgr.create_request("https://shop.example", methods, details(), options).unwrap();
+        mgr.show(id, true).unwrap();
+        assert_eq!(mgr.sheet().unwrap().selected_shipping.as_deref(), Some("std"));
+
+        // Missing address and email block confirmation
+        assert!(mgr.confirm().is_err());
+        mgr.set_payer_details(PayerDetails {
+            email: Some("a@example.com".into()),
+            shipping_address: Some(PaymentAddress { country: "US".into(), ..Default::default() }),
+            ..Default::default()
+        });
+        assert_eq!(mgr.take_events(), vec![PaymentRequestEvent::ShippingAddressChange(id)]);
+        assert_eq!(mgr.confirm(), Err(PaymentError::InvalidState));
+
+        mgr.select_shipping_option(1);
+        assert_eq!(mgr.take_events(), vec![PaymentRequestEvent::ShippingOptionChange(id)]);
+        let mut options = details().shipping_options;
+        options[0].selected = false;
+        options[1].selected = true;
+        mgr.update_with(id, PaymentDetailsUpdate {
+            total: Some(PaymentItem::new("Total", "USD", "15.00")),
+            shipping_options: Some(options),
+            ..Default::default()
+        }).unwrap();
+
+        assert_eq!(mgr.confirm(), Ok(id));
+        let response = mgr.response(id).unwrap();
+        assert_eq!(response.details, "{\"token\":\"card-1\"}");
+        assert_eq!(response.shipping_option.as_deref(), Some("fast"));
+        assert_eq!(response.payer_email.as_deref(), Some("a@example.com"));
+        assert_eq!(response.payer_name, None);
+        mgr.complete(id, PaymentComplete::Success).unwrap();
+        assert_eq!(mgr.request(id).unwrap().state, PaymentRequestState::Closed);
+    }
+
+    #[test]
+    fn test_enrolled_instrument_quota() {
+        let mut mgr = manager(false);
+        let id = mgr.create_request("https://shop.example", vec![PaymentMethodData::new("https://pay.example")], details(), PaymentOptions::default()).unwrap();
+        assert!(mgr.can_make_payment(id));
+        assert_eq!(mgr.has_enrolled_instrument(id), Ok(false));
+        assert_eq!(mgr.has_enrolled_instrument(id), Ok(false));
+
+        // Probing a different method set is rejected
+        let other = mgr.create_request("https://shop.example", vec![PaymentMethodData::new("basic-card")], details(), PaymentOptions::default()).unwrap();
+        assert_eq!(mgr.has_enrolled_instrument(other), Err(PaymentError::NotAllowed));
+        assert!(!mgr.can_make_payment(other));
+
+        // No instruments: the sheet cannot open
+        assert_eq!(mgr.show(id, true), Err(PaymentError::NotSupported));
+    }
+
+    #[test]
+    fn test_cancel_and_render() {
+        let mut mgr = manager(true);
+        let id = mgr.create_request("https://shop.example", vec![PaymentMethodData::new("https://pay.example")], details(), PaymentOptions::default()).unwrap();
+        mgr.show(id, true).unwrap();
+
+        let mut buffer = vec![0xFFFFFFFF; 400 * 300];
+        mgr.sheet().unwrap().render(&mut buffer, 400, 300);
+        assert_ne!(buffer[0], 0xFFFFFFFF);
+        assert_eq!(buffer[150 * 400 + 200], colors::BG);
+
+        mgr.move_focus(1);
+        mgr.activate_focused();
+        assert!(mgr.sheet().is_none());
+        assert_eq!(mgr.take_events(), vec![PaymentRequestEvent::Rejected(id, PaymentError::Aborted)]);
+        assert_eq!(mgr.abort(id), Err(PaymentError::InvalidState));
+    }
+}
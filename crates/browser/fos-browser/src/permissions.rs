@@ -3,6 +3,7 @@
 //! Unified permission management for browser features.
 
 use std::collections::HashMap;
+use crate::tab::TabId;
 
 /// Permission state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -123,6 +124,8 @@ pub struct PermissionsManager {
     /// Default permissions (not prompting)
     auto_grant: Vec<PermissionName>,
     auto_deny: Vec<PermissionName>,
+    /// DevTools overrides by tab; win over stored state and are never stored
+    tab_overrides: HashMap<TabId, HashMap<PermissionName, PermissionState>>,
 }
 
 impl PermissionsManager {
@@ -186,6 +189,41 @@ impl PermissionsManager {
             .insert(name, state);
     }
     
+    /// Override a permission for every origin in a tab; `None` removes it
+    pub fn set_tab_override(&mut self, tab: TabId, name: PermissionName, state: Option<PermissionState>) {
+        let overrides = self.tab_overrides.entry(tab).or_default();
+        match state {
+            Some(state) => { overrides.insert(name, state); }
+            None => { overrides.remove(&name); }
+        }
+    }
+    
+    /// Drop all overrides of a tab (DevTools detached or tab closed)
+    pub fn clear_tab_overrides(&mut self, tab: TabId) {
+        self.tab_overrides.remove(&tab);
+    }
+    
+    fn tab_override(&self, tab: TabId, name: &PermissionName) -> Option<PermissionState> {
+        self.tab_overrides.get(&tab).and_then(|o| o.get(name)).copied()
+    }
+    
+    /// Query permission state as seen by a document in `tab`
+    pub fn query_in_tab(&self, tab: TabId, origin: &str, descriptor: &PermissionDescriptor) -> PermissionStatus {
+        match self.tab_override(tab, &descriptor.name) {
+            Some(state) => PermissionStatus { name: descriptor.name.clone(), state },
+            None => self.query(origin, descriptor),
+        }
+    }
+    
+    /// Request permission from a document in `tab`; overridden permissions
+    /// resolve without prompting
+    pub fn request_in_tab(&mut self, tab: TabId, origin: &str, descriptor: &PermissionDescriptor) -> PermissionState {
+        match self.tab_override(tab, &descriptor.name) {
+            Some(state) => state,
+            None => self.request(origin, descriptor),
+        }
+    }
+    
     /// Clear all permissions for origin
     pub fn clear_origin(&mut self, origin: &str) {
         self.permissions.remove(origin);
@@ -237,4 +275,20 @@ mod tests {
         let status = mgr.query(origin, &desc);
         assert_eq!(status.state, PermissionState::Prompt);
     }
+    
+    #[test]
+    fn test_tab_overrides() {
+        let mut mgr = PermissionsManager::new();
+        let origin = "https://example.com";
+        let desc = PermissionDescriptor::new(PermissionName::Camera);
+        
+        mgr.set_tab_override(1, PermissionName::Camera, Some(PermissionState::Denied));
+        assert_eq!(mgr.request_in_tab(1, origin, &desc), PermissionState::Denied);
+        // Other tabs and the stored state are untouched
+        assert_eq!(mgr.query_in_tab(2, origin, &desc).state, PermissionState::Prompt);
+        assert_eq!(mgr.query(origin, &desc).state, PermissionState::Prompt);
+        
+        mgr.clear_tab_overrides(1);
+        assert_eq!(mgr.request_in_tab(1, origin, &desc), PermissionState::Granted);
+    }
 }
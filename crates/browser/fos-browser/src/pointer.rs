@@ -19,6 +19,9 @@ pub struct PointerEvent {
     pub pointer_type: PointerType,
     pub x: f32,
     pub y: f32,
+    /// Movement since the previous move event (`movementX`/`movementY`)
+    pub movement_x: f32,
+    pub movement_y: f32,
     pub width: f32,
     pub height: f32,
     pub pressure: f32,
@@ -64,6 +67,8 @@ impl Default for PointerEvent {
             pointer_type: PointerType::Mouse,
             x: 0.0,
             y: 0.0,
+            movement_x: 0.0,
+            movement_y: 0.0,
             width: 1.0,
             height: 1.0,
             pressure: 0.0,
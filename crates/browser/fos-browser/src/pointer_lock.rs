@@ -0,0 +1,340 @@
+//! Pointer Lock API
+//!
+//! `element.requestPointerLock()` for games and 3D viewers: the cursor is
+//! hidden and held in place, and mouse moves only report relative
+//! `movementX`/`movementY` deltas. With `unadjustedMovement` the deltas are
+//! raw device counts without OS acceleration. Escape, losing focus or
+//! `document.exitPointerLock()` release the lock; every change fires
+//! `pointerlockchange` and every refused request `pointerlockerror`.
+//!
+//! The window system does the actual cursor capture through
+//! [`CursorCapture`], which the app layer implements for its window.
+
+use fos_dom::NodeId;
+
+use crate::pointer::{PointerEvent, PointerEventType};
+
+/// Options of `requestPointerLock()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PointerLockOptions {
+    /// Report raw device deltas instead of accelerated cursor movement
+    pub unadjusted_movement: bool,
+}
+
+/// Pointer lock error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerLockError {
+    /// No cursor capture on this platform, or no raw input for
+    /// `unadjustedMovement`
+    NotSupported,
+    /// Unfocused window, or no user activation after the user exited
+    NotAllowed,
+    /// The element is not connected to the document
+    WrongDocument,
+    /// The window system refused to capture the cursor
+    CaptureFailed,
+}
+
+impl std::fmt::Display for PointerLockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotSupported => write!(f, "Pointer lock not supported"),
+            Self::NotAllowed => write!(f, "Pointer lock not allowed"),
+            Self::WrongDocument => write!(f, "Element not in document"),
+            Self::CaptureFailed => write!(f, "Cursor capture failed"),
+        }
+    }
+}
+
+impl std::error::Error for PointerLockError {}
+
+/// Event fired at the document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerLockEvent {
+    Change,
+    Error,
+}
+
+impl PointerLockEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Change => "pointerlockchange",
+            Self::Error => "pointerlockerror",
+        }
+    }
+}
+
+/// OS cursor capture, implemented by the app layer for its window
+pub trait CursorCapture: Send + std::fmt::Debug {
+    /// Hide the cursor and keep it inside the window
+    fn capture(&mut self) -> bool;
+
+    /// Show the cursor and let it move freely again
+    fn release(&mut self);
+
+    /// Move the cursor, to keep a confined cursor away from the edges
+    fn warp(&mut self, x: f32, y: f32);
+
+    /// Whether raw device motion is delivered for `unadjustedMovement`
+    fn supports_unadjusted_movement(&self) -> bool { false }
+}
+
+/// Pointer lock state of a window
+#[derive(Debug, Default)]
+pub struct PointerLockManager {
+    capture: Option<Box<dyn CursorCapture>>,
+    element: Option<NodeId>,
+    options: PointerLockOptions,
+    /// Where the cursor is held while locked
+    anchor: (f32, f32),
+    /// Last cursor position, for movement deltas
+    last_position: Option<(f32, f32)>,
+    /// The user pressed Escape; the next lock needs a new user activation
+    user_exited: bool,
+    events: Vec<PointerLockEvent>,
+}
+
+impl PointerLockManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_capture(&mut self, capture: Box<dyn CursorCapture>) {
+        self.capture = Some(capture);
+    }
+
+    /// `document.pointerLockElement`
+    pub fn pointer_lock_element(&self) -> Option<NodeId> {
+        self.element
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.element.is_some()
+    }
+
+    pub fn options(&self) -> PointerLockOptions {
+        self.options
+    }
+
+    /// `element.requestPointerLock(options)`. A document that already holds
+    /// the lock may move it to another element or change its options
+    /// without a new user activation.
+    pub fn request_pointer_lock(
+        &mut self,
+        element: NodeId,
+        connected: bool,
+        options: PointerLockOptions,
+        user_activation: bool,
+        focused: bool,
+    ) -> Result<(), PointerLockError> {
+        let result = self.try_lock(element, connected, options, user_activation, focused);
+        match result {
+            Ok(()) => self.events.push(PointerLockEvent::Change),
+            Err(_) => self.events.push(PointerLockEvent::Error),
+        }
+        result
+    }
+
+    fn try_lock(
+        &mut self,
+        element: NodeId,
+        connected: bool,
+        options: PointerLockOptions,
+        user_activation: bool,
+        focused: bool,
+    ) -> Result<(), PointerLockError> {
+        let capture = self.capture.as_mut().ok_or(PointerLockError::NotSupported)?;
+        if !connected {
+            return Err(PointerLockError::WrongDocument);
+        }
+        if options.unadjusted_movement && !capture.supports_unadjusted_movement() {
+            return Err(PointerLockError::NotSupported);
+        }
+        if self.element.is_some() {
+            self.element = Some(element);
+            self.options = options;
+            return Ok(());
+        }
+        if !focused || (self.user_exited && !user_activation) {
+            return Err(PointerLockError::NotAllowed);
+        }
+        if !capture.capture() {
+            return Err(PointerLockError::CaptureFailed);
+        }
+
+        self.element = Some(element);
+        self.options = options;
+        self.user_exited = false;
+        self.anchor = self.last_position.unwrap_or_default();
+        Ok(())
+    }
+
+    /// `document.exitPointerLock()`
+    pub fn exit_pointer_lock(&mut self) {
+        if self.element.take().is_none() {
+            return;
+        }
+        if let Some(capture) = self.capture.as_mut() {
+            capture.release();
+            // Put the cursor back where it was hidden
+            capture.warp(self.anchor.0, self.anchor.1);
+        }
+        self.last_position = Some(self.anchor);
+        self.events.push(PointerLockEvent::Change);
+    }
+
+    /// Escape key; true if it released the lock and must not reach the page
+    pub fn handle_escape(&mut self) -> bool {
+        if !self.is_locked() {
+            return false;
+        }
+        self.exit_pointer_lock();
+        self.user_exited = true;
+        true
+    }
+
+    /// The window lost focus, or the locked element left the document
+    pub fn release(&mut self) {
+        self.exit_pointer_lock();
+    }
+
+    /// Cursor moved to `(x, y)`. While locked the cursor is warped back to
+    /// the anchor, so the event keeps the lock position and only reports
+    /// the delta; returns `None` for the move caused by the warp and for
+    /// cursor moves under `unadjustedMovement`, which uses
+    /// [`Self::raw_motion`] instead.
+    pub fn cursor_moved(&mut self, x: f32, y: f32) -> Option<PointerEvent> {
+        if !self.is_locked() {
+            let (last_x, last_y) = self.last_position.unwrap_or((x, y));
+            self.last_position = Some((x, y));
+            return Some(self.move_event(x, y, x - last_x, y - last_y));
+        }
+        if self.options.unadjusted_movement {
+            return None;
+        }
+
+        let (dx, dy) = (x - self.anchor.0, y - self.anchor.1);
+        if dx == 0.0 && dy == 0.0 {
+            return None;
+        }
+        if let Some(capture) = self.capture.as_mut() {
+            capture.warp(self.anchor.0, self.anchor.1);
+        }
+        Some(self.move_event(self.anchor.0, self.anchor.1, dx, dy))
+    }
+
+    /// Raw device motion; only reported while locked with
+    /// `unadjustedMovement`
+    pub fn raw_motion(&mut self, dx: f64, dy: f64) -> Option<PointerEvent> {
+        if !self.is_locked() || !self.options.unadjusted_movement || (dx == 0.0 && dy == 0.0) {
+            return None;
+        }
+        Some(self.move_event(self.anchor.0, self.anchor.1, dx as f32, dy as f32))
+    }
+
+    /// Take `pointerlockchange`/`pointerlockerror` events to fire
+    pub fn take_events(&mut self) -> Vec<PointerLockEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn move_event(&self, x: f32, y: f32, movement_x: f32, movement_y: f32) -> PointerEvent {
+        PointerEvent {
+            event_type: PointerEventType::PointerMove,
+            x,
+            y,
+            movement_x,
+            movement_y,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default)]
+    struct Cursor {
+        captured: bool,
+        warps: Vec<(f32, f32)>,
+    }
+
+    #[derive(Debug)]
+    struct TestCapture(Arc<Mutex<Cursor>>, bool);
+
+    impl CursorCapture for TestCapture {
+        fn capture(&mut self) -> bool {
+            self.0.lock().unwrap().captured = true;
+            true
+        }
+
+        fn release(&mut self) {
+            self.0.lock().unwrap().captured = false;
+        }
+
+        fn warp(&mut self, x: f32, y: f32) {
+            self.0.lock().unwrap().warps.push((x, y));
+        }
+
+        fn supports_unadjusted_movement(&self) -> bool {
+            self.1
+        }
+    }
+
+    fn manager(raw: bool) -> (PointerLockManager, Arc<Mutex<Cursor>>) {
+        let cursor = Arc::new(Mutex::new(Cursor::default()));
+        let mut manager = PointerLockManager::new();
+        manager.set_capture(Box::new(TestCapture(cursor.clone(), raw)));
+        (manager, cursor)
+    }
+
+    #[test]
+    fn test_lock_and_movement() {
+        let (mut lock, cursor) = manager(false);
+        let canvas = NodeId(7);
+        lock.cursor_moved(100.0, 50.0);
+        lock.request_pointer_lock(canvas, true, PointerLockOptions::default(), true, true).unwrap();
+        assert!(cursor.lock().unwrap().captured);
+        assert_eq!(lock.pointer_lock_element(), Some(canvas));
+
+        // Deltas are reported at the lock position and the cursor is re-centered
+        let event = lock.cursor_moved(104.0, 47.0).unwrap();
+        assert_eq!((event.x, event.y, event.movement_x, event.movement_y), (100.0, 50.0, 4.0, -3.0));
+        assert_eq!(cursor.lock().unwrap().warps, vec![(100.0, 50.0)]);
+        assert!(lock.cursor_moved(100.0, 50.0).is_none());
+        assert!(lock.raw_motion(3.0, 1.0).is_none());
+
+        // Escape exits; locking again then needs a user gesture
+        assert!(lock.handle_escape());
+        assert!(!cursor.lock().unwrap().captured);
+        assert!(!lock.handle_escape());
+        assert_eq!(
+            lock.request_pointer_lock(canvas, true, PointerLockOptions::default(), false, true),
+            Err(PointerLockError::NotAllowed)
+        );
+        assert_eq!(
+            lock.take_events(),
+            vec![PointerLockEvent::Change, PointerLockEvent::Change, PointerLockEvent::Error]
+        );
+    }
+
+    #[test]
+    fn test_unadjusted_movement() {
+        let options = PointerLockOptions { unadjusted_movement: true };
+        let (mut lock, _) = manager(false);
+        assert_eq!(lock.request_pointer_lock(NodeId(1), true, options, true, true), Err(PointerLockError::NotSupported));
+        assert_eq!(
+            lock.request_pointer_lock(NodeId(1), false, PointerLockOptions::default(), true, true),
+            Err(PointerLockError::WrongDocument)
+        );
+
+        let (mut lock, _) = manager(true);
+        lock.request_pointer_lock(NodeId(1), true, options, true, true).unwrap();
+        assert!(lock.cursor_moved(10.0, 10.0).is_none());
+        let event = lock.raw_motion(2.5, -1.0).unwrap();
+        assert_eq!((event.movement_x, event.movement_y), (2.5, -1.0));
+        lock.release();
+        assert!(!lock.is_locked());
+    }
+}
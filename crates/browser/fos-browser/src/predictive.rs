@@ -29,6 +29,8 @@ pub struct PredictiveNetwork {
     page_links: HashSet<String>,
     /// Preconnected hosts
     preconnected: HashSet<String>,
+    /// Data saver is on: no speculative fetching
+    save_data: bool,
 }
 
 impl PredictiveNetwork {
@@ -40,9 +42,20 @@ impl PredictiveNetwork {
             coalescer: RequestCoalescer::new(5, 100), // Batch 5 requests or 100ms timeout
             page_links: HashSet::new(),
             preconnected: HashSet::new(),
+            save_data: false,
         }
     }
     
+    /// Follow the data saver preference; while on, links are not
+    /// speculatively resolved and prefetch/prerender hints are ignored
+    pub fn set_save_data(&mut self, enabled: bool) {
+        self.save_data = enabled;
+    }
+    
+    pub fn save_data(&self) -> bool {
+        self.save_data
+    }
+    
     // === DNS Prefetching ===
     
     /// Record a page visit for prediction learning
@@ -88,7 +101,9 @@ impl PredictiveNetwork {
                 if let Some(host) = parsed.host_str() {
                     self.page_links.insert(host.to_string());
                     // Prefetch DNS for all visible links
-                    self.dns.prefetch(host);
+                    if !self.save_data {
+                        self.dns.prefetch(host);
+                    }
                 }
             }
         }
@@ -96,6 +111,9 @@ impl PredictiveNetwork {
     
     /// Get predicted next navigations
     pub fn predict_next(&self) -> Vec<String> {
+        if self.save_data {
+            return Vec::new();
+        }
         self.page_links.iter().take(5).cloned().collect()
     }
     
@@ -122,7 +140,7 @@ impl PredictiveNetwork {
     }
     
     /// Get shared resource by hash
-    pub fn get_shared_resource(&self, hash: u64) -> Option<&SharedResource> {
+    pub fn get_shared_resource(&self, hash: u64) -> Option<SharedResource> {
         self.cross_tab.get(hash)
     }
     
@@ -222,6 +240,10 @@ impl ResourceHint {
     
     /// Apply this hint to the predictive network
     pub fn apply(&self, network: &mut PredictiveNetwork) {
+        // Fetches for a possible next page cost data the user asked to save
+        if network.save_data && matches!(self.hint_type, HintType::Prefetch | HintType::Prerender) {
+            return;
+        }
         if let Ok(parsed) = fos_engine::url::Url::parse(&self.url) {
             if let Some(host) = parsed.host_str() {
                 match self.hint_type {
@@ -283,4 +305,19 @@ mod tests {
         assert!(hint.is_some());
         assert_eq!(hint.unwrap().hint_type, HintType::Preconnect);
     }
+    
+    #[test]
+    fn test_save_data_skips_speculation() {
+        let mut net = PredictiveNetwork::new();
+        net.set_save_data(true);
+        net.update_page_links(vec!["https://example.com/next".to_string()]);
+        assert!(net.predict_next().is_empty());
+        
+        let prerender = ResourceHint::from_link("prerender", "https://example.com/next", false, None).unwrap();
+        prerender.apply(&mut net);
+        assert!(!net.is_preconnected("example.com"));
+        let preconnect = ResourceHint::from_link("preconnect", "https://cdn.example.com", true, None).unwrap();
+        preconnect.apply(&mut net);
+        assert!(net.is_preconnected("cdn.example.com"));
+    }
 }
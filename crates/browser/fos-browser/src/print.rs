@@ -1,9 +1,25 @@
 //! Print functionality
 //!
-//! Print page to PDF or printer.
+//! Print page to PDF or printer. Pages are laid out again for paged media,
+//! with `@media print` rules and the paper's content box as the viewport,
+//! rasterized one image per page and written out as a PDF. Painted text
+//! is laid invisibly over each image so it can be selected and searched.
 
 use std::path::PathBuf;
 
+use crate::renderer::{PageRenderer, PrintedPage, TextRun};
+
+/// PDF points per CSS pixel: points are 1/72 inch, CSS pixels 1/96
+const PT_PER_CSS_PX: f32 = 0.75;
+/// Device pixels per CSS pixel pages are rasterized at (192 dpi)
+const PRINT_RESOLUTION: f32 = 2.0;
+/// Font size of the page headers and footers, in points
+const HEADER_FONT_SIZE: f32 = 8.0;
+
+fn mm_to_pt(mm: f32) -> f32 {
+    mm * 72.0 / 25.4
+}
+
 /// Print settings
 #[derive(Debug, Clone)]
 pub struct PrintSettings {
@@ -28,6 +44,24 @@ impl Default for PrintSettings {
     }
 }
 
+impl PrintSettings {
+    /// Page size in points, turned for the orientation
+    pub fn page_size_pt(&self) -> (f32, f32) {
+        let (w, h) = self.paper_size.dimensions_pt();
+        match self.orientation {
+            Orientation::Portrait => (w, h),
+            Orientation::Landscape => (h, w),
+        }
+    }
+    
+    /// Size of the page inside the margins, in points
+    pub fn content_size_pt(&self) -> (f32, f32) {
+        let (w, h) = self.page_size_pt();
+        let m = &self.margins;
+        (w - mm_to_pt(m.left + m.right), h - mm_to_pt(m.top + m.bottom))
+    }
+}
+
 /// Paper size
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PaperSize {
@@ -55,7 +89,7 @@ impl PaperSize {
     /// Get dimensions in points (72 dpi)
     pub fn dimensions_pt(&self) -> (f32, f32) {
         let (w, h) = self.dimensions_mm();
-        (w as f32 * 2.834, h as f32 * 2.834)
+        (mm_to_pt(w as f32), mm_to_pt(h as f32))
     }
 }
 
@@ -110,34 +144,109 @@ impl PrintManager {
     
     /// Print to PDF file
     pub fn print_to_pdf(&self, html: &str, url: &str, output: PathBuf) -> Result<(), PrintError> {
-        // For now, just write a simple PDF header
-        // A real implementation would use a PDF library
-        use std::fs::File;
-        use std::io::Write;
+        let pdf = self.render_pdf(html, url)?;
+        std::fs::write(&output, pdf).map_err(|e| PrintError::IoError(e.to_string()))?;
         
-        let mut file = File::create(&output).map_err(|e| PrintError::IoError(e.to_string()))?;
+        log::info!("PDF saved to {:?}", output);
+        Ok(())
+    }
+    
+    /// Lay `html` out for print and encode its pages as a PDF
+    pub fn render_pdf(&self, html: &str, url: &str) -> Result<Vec<u8>, PrintError> {
+        let settings = &self.settings;
+        let (content_width, content_height) = settings.content_size_pt();
+        if content_width <= 0.0 || content_height <= 0.0 {
+            return Err(PrintError::PrintFailed("Margins leave no room on the page".into()));
+        }
         
-        // Minimal PDF structure
-        let content = format!(
-            "%PDF-1.4\n\
-            1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n\
-            2 0 obj\n<< /Type /Pages /Kids [3 0 R] /Count 1 >>\nendobj\n\
-            3 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R >>\nendobj\n\
-            4 0 obj\n<< /Length 44 >>\nstream\nBT /F1 12 Tf 72 720 Td (Page from: {}) Tj ET\nendstream\nendobj\n\
-            xref\n0 5\n\
-            0000000000 65535 f \n\
-            0000000009 00000 n \n\
-            0000000058 00000 n \n\
-            0000000115 00000 n \n\
-            0000000214 00000 n \n\
-            trailer\n<< /Size 5 /Root 1 0 R >>\nstartxref\n307\n%%EOF",
-            url
-        );
+        // The print scale zooms the page the way page zoom does
+        let scale = if settings.scale > 0.0 { settings.scale } else { 1.0 };
+        let width = (content_width / PT_PER_CSS_PX * PRINT_RESOLUTION).round().max(1.0) as u32;
+        let height = (content_height / PT_PER_CSS_PX * PRINT_RESOLUTION).round().max(1.0) as u32;
+        let mut renderer = PageRenderer::new(width, height);
+        renderer.set_device_pixel_ratio(PRINT_RESOLUTION * scale);
         
-        file.write_all(content.as_bytes()).map_err(|e| PrintError::IoError(e.to_string()))?;
+        let pages = renderer.render_pages(html, url);
+        if pages.is_empty() {
+            return Err(PrintError::PrintFailed("Nothing to print".into()));
+        }
+        Ok(self.encode_pdf(&pages, url, PT_PER_CSS_PX * scale))
+    }
+    
+    /// Write `pages` as a PDF, `pt_per_px` points to each CSS pixel
+    fn encode_pdf(&self, pages: &[PrintedPage], url: &str, pt_per_px: f32) -> Vec<u8> {
+        let settings = &self.settings;
+        let (page_width, page_height) = settings.page_size_pt();
+        let (content_width, content_height) = settings.content_size_pt();
+        let left = mm_to_pt(settings.margins.left);
+        let top = page_height - mm_to_pt(settings.margins.top);
         
-        log::info!("PDF saved to {:?}", output);
-        Ok(())
+        let mut pdf = PdfWriter::new();
+        let catalog = pdf.reserve();
+        let page_tree = pdf.reserve();
+        let font = pdf.reserve();
+        let mut kids = Vec::new();
+        
+        for (index, page) in pages.iter().enumerate() {
+            let page_id = pdf.reserve();
+            let contents = pdf.reserve();
+            let image = pdf.reserve();
+            kids.push(page_id);
+            
+            let rgb: Vec<u8> = page.pixels.chunks_exact(4).flat_map(|px| [px[0], px[1], px[2]]).collect();
+            pdf.stream(image, &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /RunLengthDecode",
+                page.width, page.height,
+            ), &run_length_encode(&rgb));
+            
+            let mut content = format!(
+                "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im0 Do Q\n",
+                content_width, content_height, left, top - content_height,
+            );
+            // Invisible text over the image, for selecting and searching
+            content.push_str("BT 3 Tr\n");
+            for run in &page.text {
+                content.push_str(&text_run_operators(run, left, top, pt_per_px));
+            }
+            content.push_str("ET\n");
+            if settings.headers_footers {
+                let margin_top = mm_to_pt(settings.margins.top);
+                let margin_bottom = mm_to_pt(settings.margins.bottom);
+                let footer = format!("{} / {}", index + 1, pages.len());
+                let footer_x = page_width - mm_to_pt(settings.margins.right) - footer.len() as f32 * HEADER_FONT_SIZE * 0.5;
+                content.push_str(&format!(
+                    "BT 0 g /F1 {size} Tf {:.2} {:.2} Td {} Tj ET\nBT 0 g /F1 {size} Tf {:.2} {:.2} Td {} Tj ET\n",
+                    left, page_height - margin_top / 2.0 - HEADER_FONT_SIZE / 3.0, pdf_string(url),
+                    footer_x, margin_bottom / 2.0 - HEADER_FONT_SIZE / 3.0, pdf_string(&footer),
+                    size = HEADER_FONT_SIZE,
+                ));
+            }
+            pdf.stream(contents, "", content.as_bytes());
+            
+            let mut annots = Vec::new();
+            for link in &page.links {
+                let annot = pdf.reserve();
+                let x0 = left + link.x * pt_per_px;
+                let y1 = top - link.y * pt_per_px;
+                pdf.object(annot, &format!(
+                    "<< /Type /Annot /Subtype /Link /Rect [{:.2} {:.2} {:.2} {:.2}] /Border [0 0 0] /A << /S /URI /URI {} >> >>",
+                    x0, y1 - link.height * pt_per_px, x0 + link.width * pt_per_px, y1, pdf_string(&link.href),
+                ));
+                annots.push(format!("{} 0 R", annot));
+            }
+            
+            pdf.object(page_id, &format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {:.2} {:.2}] /Contents {} 0 R \
+                /Resources << /Font << /F1 {} 0 R >> /XObject << /Im0 {} 0 R >> >> /Annots [{}] >>",
+                page_tree, page_width, page_height, contents, font, image, annots.join(" "),
+            ));
+        }
+        
+        pdf.object(font, "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>");
+        let kids: Vec<String> = kids.iter().map(|id| format!("{} 0 R", id)).collect();
+        pdf.object(page_tree, &format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()));
+        pdf.object(catalog, &format!("<< /Type /Catalog /Pages {} 0 R >>", page_tree));
+        pdf.finish(catalog)
     }
     
     /// Print using system print dialog (Linux)
@@ -190,6 +299,115 @@ impl std::fmt::Display for PrintError {
 
 impl std::error::Error for PrintError {}
 
+/// Text operators placing `run` on the page, stretched to the width it
+/// was painted at since Helvetica's metrics differ from the page's font
+fn text_run_operators(run: &TextRun, left: f32, top: f32, pt_per_px: f32) -> String {
+    let size = run.font_size * pt_per_px;
+    // Helvetica averages about half an em per character
+    let natural = run.text.chars().count() as f32 * size * 0.5;
+    let stretch = if natural > 0.0 { (run.width * pt_per_px / natural * 100.0).clamp(10.0, 1000.0) } else { 100.0 };
+    format!(
+        "/F1 {:.2} Tf {:.1} Tz 1 0 0 1 {:.2} {:.2} Tm {} Tj\n",
+        size, stretch, left + run.x * pt_per_px, top - run.baseline * pt_per_px, pdf_string(&run.text),
+    )
+}
+
+/// A PDF literal string in WinAnsi encoding; characters it lacks become `?`
+fn pdf_string(text: &str) -> String {
+    let mut out = String::from("(");
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            // WinAnsi matches Latin-1 in this range
+            '\u{a0}'..='\u{ff}' => out.push_str(&format!("\\{:03o}", c as u32)),
+            _ => out.push('?'),
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// PackBits-style encoding for the `/RunLengthDecode` filter: a length
+/// byte of 0-127 copies that many plus one bytes, 129-255 repeats the next
+/// byte 257 minus it times, and 128 ends the data
+fn run_length_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 8);
+    let mut i = 0;
+    while i < data.len() {
+        let mut run = 1;
+        while i + run < data.len() && run < 128 && data[i + run] == data[i] {
+            run += 1;
+        }
+        if run > 1 {
+            out.push((257 - run) as u8);
+            out.push(data[i]);
+            i += run;
+            continue;
+        }
+        
+        // Copy bytes literally up to where the next run starts
+        let start = i;
+        while i < data.len() && i - start < 128 && !(i + 1 < data.len() && data[i + 1] == data[i]) {
+            i += 1;
+        }
+        out.push((i - start - 1) as u8);
+        out.extend_from_slice(&data[start..i]);
+    }
+    out.push(128);
+    out
+}
+
+/// Writes numbered objects and the cross-reference table pointing at them
+struct PdfWriter {
+    out: Vec<u8>,
+    /// Byte offset of each object by number less one; 0 until written
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        // The binary comment tells transfer tools the file isn't text
+        Self { out: b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec(), offsets: Vec::new() }
+    }
+    
+    /// Number an object to be written later
+    fn reserve(&mut self) -> usize {
+        self.offsets.push(0);
+        self.offsets.len()
+    }
+    
+    fn object(&mut self, id: usize, body: &str) {
+        self.offsets[id - 1] = self.out.len();
+        self.out.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", id, body).as_bytes());
+    }
+    
+    /// Write a stream object; `dict` holds its entries besides `/Length`
+    fn stream(&mut self, id: usize, dict: &str, data: &[u8]) {
+        self.offsets[id - 1] = self.out.len();
+        self.out.extend_from_slice(format!("{} 0 obj\n<< {} /Length {} >>\nstream\n", id, dict, data.len()).as_bytes());
+        self.out.extend_from_slice(data);
+        self.out.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+    
+    fn finish(mut self, root: usize) -> Vec<u8> {
+        let xref = self.out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1, root, xref,
+        ));
+        self.out.extend_from_slice(table.as_bytes());
+        self.out
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,4 +418,55 @@ mod tests {
         assert_eq!(w, 210);
         assert_eq!(h, 297);
     }
+    
+    #[test]
+    fn test_run_length_encode() {
+        let data = [7u8, 7, 7, 7, 1, 2, 3, 9, 9]
+            .into_iter()
+            .chain(std::iter::repeat_n(0, 300))
+            .collect::<Vec<_>>();
+        let encoded = run_length_encode(&data);
+        assert_eq!(encoded.last(), Some(&128));
+        
+        let mut decoded = Vec::new();
+        let mut i = 0;
+        while encoded[i] != 128 {
+            let length = encoded[i] as usize;
+            if length < 128 {
+                decoded.extend_from_slice(&encoded[i + 1..i + 2 + length]);
+                i += 2 + length;
+            } else {
+                decoded.extend(std::iter::repeat_n(encoded[i + 1], 257 - length));
+                i += 2;
+            }
+        }
+        assert_eq!(decoded, data);
+    }
+    
+    #[test]
+    fn test_render_pdf_pages() {
+        let mut html = String::from("<html><body>");
+        for i in 0..120 {
+            html.push_str(&format!("<p>Printed paragraph {}</p>", i));
+        }
+        html.push_str("</body></html>");
+        
+        let pdf = PrintManager::new().render_pdf(&html, "https://example.com/").unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.ends_with("%%EOF\n"));
+        let count: usize = text.split("/Count ").nth(1).unwrap()
+            .split_whitespace().next().unwrap().parse().unwrap();
+        assert!(count > 1);
+        assert_eq!(text.matches("/Type /Page ").count(), count);
+        assert!(text.contains("Printed"));
+        
+        // The cross-reference table points at each object
+        let xref: usize = text.rsplit("startxref\n").next().unwrap()
+            .lines().next().unwrap().parse().unwrap();
+        let table = std::str::from_utf8(&pdf[xref..]).unwrap();
+        assert!(table.starts_with("xref"));
+        let offset: usize = table.lines().nth(3).unwrap()[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(b"1 0 obj"));
+    }
 }
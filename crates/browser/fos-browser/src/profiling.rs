@@ -1,8 +1,9 @@
 //! Performance Profiling Integration
 //!
-//! Integrates fos-devtools performance panel: frame timing, marks, measures, memory.
+//! Integrates fos-devtools performance panel: frame timing, marks, measures, memory,
+//! and the trace-event timeline of the rendering pipeline.
 
-use fos_devtools::{PerformancePanel, MemoryInfo};
+use fos_devtools::{PerformancePanel, MemoryInfo, FrameTimeline, SpanId, TraceKind, Tracer};
 use fos_devtools::performance::EntryType;
 use std::time::Instant;
 
@@ -96,6 +97,33 @@ impl PerformanceProfiler {
         }
     }
     
+    // === Trace Events ===
+    
+    /// Tracer the pipeline records into; a no-op unless profiling
+    pub fn tracer_mut(&mut self) -> &mut Tracer {
+        self.panel.tracer_mut()
+    }
+    
+    /// Open a trace slice, closed with `end_trace`
+    pub fn begin_trace(&mut self, kind: TraceKind) -> SpanId {
+        self.panel.tracer_mut().begin(kind)
+    }
+    
+    /// Close a trace slice
+    pub fn end_trace(&mut self, span: SpanId) {
+        self.panel.tracer_mut().end(span);
+    }
+    
+    /// Frames recorded so far with their time split by pipeline stage
+    pub fn frame_timeline(&self) -> FrameTimeline {
+        self.panel.frame_timeline()
+    }
+    
+    /// Recording as Chrome trace-event JSON (load in Perfetto)
+    pub fn export_trace(&self) -> String {
+        self.panel.export_trace()
+    }
+    
     // === Marks and Measures ===
     
     /// Add a performance mark
@@ -243,4 +271,23 @@ mod tests {
         assert_eq!(summary.mark_count, 2);
         assert_eq!(summary.measure_count, 1);
     }
+    
+    #[test]
+    fn test_trace_recording() {
+        let mut profiler = PerformanceProfiler::new();
+        let span = profiler.begin_trace(TraceKind::Layout);
+        profiler.end_trace(span);
+        assert!(!profiler.export_trace().contains("\"Layout\""));
+        
+        profiler.start();
+        profiler.begin_frame();
+        let paint = profiler.begin_trace(TraceKind::Paint);
+        profiler.end_trace(paint);
+        profiler.end_frame();
+        profiler.stop();
+        
+        let timeline = profiler.frame_timeline();
+        assert_eq!(timeline.frames.len(), 1);
+        assert!(profiler.export_trace().contains("\"Paint\""));
+    }
 }
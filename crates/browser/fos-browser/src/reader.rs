@@ -0,0 +1,423 @@
+//! Reader Mode
+//!
+//! Extracts the article from a page for the reading view. Paragraphs are
+//! scored by length and commas, and their scores go to the parent and, by
+//! half, the grandparent; containers start from a tag weight and a bonus or
+//! penalty for class and ID names, and end scaled down by their link
+//! density. The best container and its well-scored siblings are kept, with
+//! navigation, sidebars, ads and link lists stripped, and written out as a
+//! plain document styled by fos-a11y's reading mode settings.
+
+use std::collections::HashMap;
+use fos_a11y::ReadingModeSettings;
+use fos_dom::url::Url;
+use fos_dom::{Document, DomTree, NodeId};
+
+/// Elements whose content is never part of the article
+const SKIP_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "iframe", "svg", "canvas",
+    "form", "button", "input", "select", "textarea", "object", "embed",
+];
+/// Page furniture around the article
+const BOILERPLATE_TAGS: &[&str] = &["nav", "aside", "footer", "header", "menu", "dialog"];
+/// Landmark roles of page furniture
+const BOILERPLATE_ROLES: &[&str] = &["navigation", "complementary", "banner", "contentinfo", "dialog"];
+/// Class and ID words of boilerplate
+const UNLIKELY_NAMES: &[&str] = &[
+    "ad", "ads", "advert", "advertisement", "banner", "breadcrumb", "comment", "comments",
+    "cookie", "footer", "menu", "nav", "popup", "promo", "related", "share", "sidebar",
+    "social", "sponsor", "subscribe", "widget",
+];
+/// Class and ID words of article containers
+const LIKELY_NAMES: &[&str] = &["article", "body", "content", "entry", "main", "post", "story", "text"];
+/// Elements written out as they are; others are replaced by their content
+const KEPT_TAGS: &[&str] = &[
+    "p", "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "li", "dl", "dt", "dd",
+    "blockquote", "pre", "code", "em", "strong", "b", "i", "sub", "sup",
+    "figure", "figcaption", "table", "thead", "tbody", "tr", "th", "td",
+];
+
+/// Shortest paragraph that counts towards a score
+const MIN_PARAGRAPH_LENGTH: usize = 25;
+/// Shortest article worth a reading view
+const MIN_ARTICLE_LENGTH: usize = 200;
+
+/// Article content of a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Article {
+    pub title: String,
+    pub byline: Option<String>,
+    /// Simplified HTML of the article body
+    pub content: String,
+    /// Characters of article text
+    pub length: usize,
+}
+
+impl Article {
+    /// The reading view: the article as a document of its own, styled by
+    /// `settings`
+    pub fn to_html(&self, settings: &ReadingModeSettings) -> String {
+        let byline = self.byline.as_ref()
+            .map(|b| format!("<p class=\"byline\">{}</p>\n", escape(b)))
+            .unwrap_or_default();
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{css}</style>\n</head>\n<body>\n<article class=\"reading-mode\">\n<h1>{title}</h1>\n{byline}{content}\n</article>\n</body>\n</html>\n",
+            title = escape(&self.title),
+            css = settings.to_css(),
+            byline = byline,
+            content = self.content,
+        )
+    }
+}
+
+/// Extract the article of a page at `url`; `None` if there is too little
+/// text to be an article
+pub fn extract(html: &str, url: &str) -> Option<Article> {
+    let document = fos_html::parse_with_url(html, url);
+    extract_from_document(&document, url)
+}
+
+/// Extract the article of a parsed document
+pub fn extract_from_document(document: &Document, url: &str) -> Option<Article> {
+    let tree = document.tree();
+    let scores = score_candidates(tree, document.body());
+    let (&best, &best_score) = scores.iter()
+        .max_by(|a, b| a.1.total_cmp(b.1).then(b.0.0.cmp(&a.0.0)))?;
+    
+    // The best container, with siblings that look like more of the article
+    let mut nodes = Vec::new();
+    let parent = tree.get(best).map_or(NodeId::NONE, |n| n.parent);
+    if parent.is_valid() {
+        let threshold = (best_score * 0.2).max(10.0);
+        for (sibling, _) in tree.children(parent) {
+            let keep = sibling == best
+                || scores.get(&sibling).is_some_and(|&s| s >= threshold)
+                || (tag(tree, sibling).as_deref() == Some("p") && {
+                    let text = text_of(tree, sibling);
+                    text.chars().count() > 80 && link_density(tree, sibling) < 0.25
+                });
+            if keep {
+                nodes.push(sibling);
+            }
+        }
+    } else {
+        nodes.push(best);
+    }
+    
+    let base = Url::parse(url).ok();
+    let mut content = String::new();
+    let mut length = 0;
+    for node in nodes {
+        length += text_of(tree, node).chars().count();
+        write_node(tree, node, base.as_ref(), false, &mut content);
+    }
+    if length < MIN_ARTICLE_LENGTH {
+        return None;
+    }
+    
+    let mut title = collapse_whitespace(&document.title());
+    if title.is_empty() {
+        title = find_element(tree, document.body(), &|t, id| tag(t, id).as_deref() == Some("h1"))
+            .map(|h1| text_of(tree, h1))
+            .unwrap_or_else(|| url.to_string());
+    }
+    Some(Article { title, byline: byline(document), content, length })
+}
+
+/// Score the containers of paragraphs under `root`
+fn score_candidates(tree: &DomTree, root: NodeId) -> HashMap<NodeId, f64> {
+    let mut scores: HashMap<NodeId, f64> = HashMap::new();
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        let Some(name) = tag(tree, id) else { continue };
+        if SKIP_TAGS.contains(&name.as_str()) || is_boilerplate(tree, id, &name) {
+            continue;
+        }
+        stack.extend(tree.children(id).map(|(child, _)| child));
+        
+        let paragraph = matches!(name.as_str(), "p" | "pre" | "td")
+            || (name == "div" && !has_block_children(tree, id));
+        if !paragraph {
+            continue;
+        }
+        let text = text_of(tree, id);
+        let len = text.chars().count();
+        if len < MIN_PARAGRAPH_LENGTH {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (len / 100).min(3) as f64;
+        
+        let parent = tree.get(id).map_or(NodeId::NONE, |n| n.parent);
+        let grandparent = tree.get(parent).map_or(NodeId::NONE, |n| n.parent);
+        for (ancestor, share) in [(parent, 1.0), (grandparent, 0.5)] {
+            let Some(ancestor_tag) = tag(tree, ancestor) else { continue };
+            *scores.entry(ancestor).or_insert_with(|| initial_score(tree, ancestor, &ancestor_tag)) += score * share;
+        }
+    }
+    
+    for (&id, score) in scores.iter_mut() {
+        *score *= 1.0 - link_density(tree, id);
+    }
+    scores
+}
+
+fn initial_score(tree: &DomTree, id: NodeId, tag: &str) -> f64 {
+    let base = match tag {
+        "article" => 10.0,
+        "div" | "section" | "main" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "address" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    base + class_weight(tree, id)
+}
+
+/// +25 for class or ID words of article containers, -25 for boilerplate
+fn class_weight(tree: &DomTree, id: NodeId) -> f64 {
+    let mut weight = 0.0;
+    for name in ["class", "id"] {
+        let Some(value) = attribute(tree, id, name) else { continue };
+        let words: Vec<String> = value.split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_ascii_lowercase())
+            .collect();
+        if words.iter().any(|w| UNLIKELY_NAMES.contains(&w.as_str())) {
+            weight -= 25.0;
+        }
+        if words.iter().any(|w| LIKELY_NAMES.contains(&w.as_str())) {
+            weight += 25.0;
+        }
+    }
+    weight
+}
+
+fn is_boilerplate(tree: &DomTree, id: NodeId, tag: &str) -> bool {
+    BOILERPLATE_TAGS.contains(&tag)
+        || attribute(tree, id, "role").is_some_and(|r| BOILERPLATE_ROLES.contains(&r.to_ascii_lowercase().as_str()))
+        || attribute(tree, id, "hidden").is_some()
+        || attribute(tree, id, "aria-hidden") == Some("true")
+        || class_weight(tree, id) < 0.0
+}
+
+fn has_block_children(tree: &DomTree, id: NodeId) -> bool {
+    tree.children(id).any(|(child, _)| {
+        tag(tree, child).is_some_and(|t| matches!(t.as_str(),
+            "p" | "div" | "section" | "article" | "table" | "ul" | "ol" | "pre" | "blockquote"
+            | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "figure"))
+    })
+}
+
+/// Share of the text under `id` that is link text
+fn link_density(tree: &DomTree, id: NodeId) -> f64 {
+    let total = text_of(tree, id).chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+    let mut links = 0;
+    let mut stack = vec![id];
+    while let Some(node) = stack.pop() {
+        if tag(tree, node).as_deref() == Some("a") {
+            links += text_of(tree, node).chars().count();
+        } else {
+            stack.extend(tree.children(node).map(|(child, _)| child));
+        }
+    }
+    links as f64 / total as f64
+}
+
+/// Text under `id`, whitespace collapsed
+fn text_of(tree: &DomTree, id: NodeId) -> String {
+    let mut text = String::new();
+    collect_text(tree, id, &mut text);
+    collapse_whitespace(&text)
+}
+
+fn collect_text(tree: &DomTree, id: NodeId, out: &mut String) {
+    let Some(node) = tree.get(id) else { return };
+    if let Some(text) = node.as_text() {
+        out.push_str(text);
+        return;
+    }
+    if tag(tree, id).is_some_and(|t| SKIP_TAGS.contains(&t.as_str())) {
+        return;
+    }
+    for (child, _) in tree.children(id) {
+        collect_text(tree, child, out);
+    }
+    out.push(' ');
+}
+
+/// Write the article HTML for `id`
+fn write_node(tree: &DomTree, id: NodeId, base: Option<&Url>, pre: bool, out: &mut String) {
+    let Some(node) = tree.get(id) else { return };
+    if let Some(text) = node.as_text() {
+        if pre {
+            out.push_str(&escape(text));
+        } else {
+            let collapsed: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if text.starts_with(char::is_whitespace) && !collapsed.is_empty() {
+                out.push(' ');
+            }
+            out.push_str(&escape(&collapsed));
+            if text.ends_with(char::is_whitespace) {
+                out.push(' ');
+            }
+        }
+        return;
+    }
+    let Some(name) = tag(tree, id) else {
+        return;
+    };
+    if SKIP_TAGS.contains(&name.as_str()) || is_boilerplate(tree, id, &name) {
+        return;
+    }
+    // Lists of links inside the article, such as "read more" blocks
+    let text_len = text_of(tree, id).chars().count();
+    if matches!(name.as_str(), "div" | "section" | "ul" | "ol" | "p") && text_len < 200 && link_density(tree, id) > 0.5 {
+        return;
+    }
+    
+    let write_children = |out: &mut String| {
+        for (child, _) in tree.children(id) {
+            write_node(tree, child, base, pre || name == "pre", out);
+        }
+    };
+    match name.as_str() {
+        "br" => out.push_str("<br>"),
+        "hr" => out.push_str("<hr>"),
+        "img" => {
+            let Some(src) = attribute(tree, id, "src").and_then(|s| resolve(base, s)) else { return };
+            let alt = attribute(tree, id, "alt").unwrap_or("");
+            out.push_str(&format!("<img src=\"{}\" alt=\"{}\">", escape(&src), escape(alt)));
+        }
+        "a" => match attribute(tree, id, "href").and_then(|h| resolve(base, h)) {
+            Some(href) => {
+                out.push_str(&format!("<a href=\"{}\">", escape(&href)));
+                write_children(out);
+                out.push_str("</a>");
+            }
+            None => write_children(out),
+        },
+        "p" if text_len == 0 && find_element(tree, id, &|t, n| tag(t, n).as_deref() == Some("img")).is_none() => {}
+        kept if KEPT_TAGS.contains(&kept) => {
+            out.push_str(&format!("<{}>", kept));
+            write_children(out);
+            out.push_str(&format!("</{}>", kept));
+        }
+        _ => write_children(out),
+    }
+}
+
+/// Author from `<meta name="author">` or a byline element
+fn byline(document: &Document) -> Option<String> {
+    let tree = document.tree();
+    let meta = find_element(tree, document.head(), &|t, id| {
+        tag(t, id).as_deref() == Some("meta") && attribute(t, id, "name").is_some_and(|n| n.eq_ignore_ascii_case("author"))
+    });
+    if let Some(author) = meta.and_then(|id| attribute(tree, id, "content")) {
+        let author = collapse_whitespace(author);
+        if !author.is_empty() {
+            return Some(author);
+        }
+    }
+    let element = find_element(tree, document.body(), &|t, id| {
+        attribute(t, id, "rel") == Some("author")
+            || ["class", "id"].iter().filter_map(|a| attribute(t, id, a)).any(|v| {
+                v.split(|c: char| !c.is_ascii_alphanumeric()).any(|w| w.eq_ignore_ascii_case("byline") || w.eq_ignore_ascii_case("author"))
+            })
+    })?;
+    let text = text_of(tree, element);
+    (!text.is_empty() && text.chars().count() < 100).then_some(text)
+}
+
+/// First element under `root`, in document order, matching `predicate`
+fn find_element(tree: &DomTree, root: NodeId, predicate: &dyn Fn(&DomTree, NodeId) -> bool) -> Option<NodeId> {
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        if tree.get(id).and_then(|n| n.as_element()).is_some() && predicate(tree, id) {
+            return Some(id);
+        }
+        let children: Vec<NodeId> = tree.children(id).map(|(child, _)| child).collect();
+        stack.extend(children.into_iter().rev());
+    }
+    None
+}
+
+fn tag(tree: &DomTree, id: NodeId) -> Option<String> {
+    let elem = tree.get(id)?.as_element()?;
+    Some(tree.resolve(elem.name.local).to_ascii_lowercase())
+}
+
+fn attribute<'a>(tree: &'a DomTree, node: NodeId, name: &str) -> Option<&'a str> {
+    let elem = tree.get(node)?.as_element()?;
+    elem.attrs.iter()
+        .find(|a| tree.resolve(a.name.local).eq_ignore_ascii_case(name))
+        .map(|a| a.value.as_str())
+}
+
+/// Absolute http(s) URL of a link or image; other schemes are dropped
+fn resolve(base: Option<&Url>, href: &str) -> Option<String> {
+    let url = match base {
+        Some(base) => base.join(href.trim()).ok()?,
+        None => Url::parse(href.trim()).ok()?,
+    };
+    let url = url.to_string();
+    (url.starts_with("https://") || url.starts_with("http://")).then_some(url)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const PAGE: &str = r#"<html><head><title>Rust in the browser</title><meta name="author" content="Ada Lovelace"></head>
+<body>
+<nav><a href="/">Home</a> <a href="/news">News</a> <a href="/about">About</a></nav>
+<div class="sidebar"><p>Subscribe to our newsletter for weekly updates, offers, and more news.</p></div>
+<div id="main-content">
+<h2>Why it matters</h2>
+<p>Browser engines are large, complicated programs, and memory safety bugs in them are a common source of security vulnerabilities.</p>
+<p>Writing an engine in a memory-safe language removes whole classes of those bugs, while keeping performance close to C++.</p>
+<p>See <a href="/docs/guide">the guide</a> for details, including build instructions, supported platforms, and known limitations.</p>
+<img src="diagram.png" alt="Architecture">
+<script>track();</script>
+<ul class="share"><li><a href="https://social.example/share">Share</a></li></ul>
+</div>
+<footer><p>Copyright 2024, Example News. All rights reserved, worldwide, forever.</p></footer>
+</body></html>"#;
+    
+    #[test]
+    fn test_extract_article() {
+        let article = extract(PAGE, "https://news.example.com/story/1").unwrap();
+        assert_eq!(article.title, "Rust in the browser");
+        assert_eq!(article.byline.as_deref(), Some("Ada Lovelace"));
+        assert!(article.content.contains("<h2>Why it matters</h2>"));
+        assert!(article.content.contains("memory-safe language"));
+        assert!(article.content.contains("<a href=\"https://news.example.com/docs/guide\">the guide</a>"));
+        assert!(article.content.contains("<img src=\"https://news.example.com/story/diagram.png\" alt=\"Architecture\">"));
+        
+        // Navigation, sidebar, footer, scripts and share links are gone
+        for boilerplate in ["Home", "newsletter", "Copyright", "track()", "Share"] {
+            assert!(!article.content.contains(boilerplate), "{}", boilerplate);
+        }
+        
+        let html = article.to_html(&ReadingModeSettings::dark());
+        assert!(html.contains("class=\"reading-mode\""));
+        assert!(html.contains("#1a1a1a"));
+        assert!(html.contains("<p class=\"byline\">Ada Lovelace</p>"));
+    }
+    
+    #[test]
+    fn test_not_an_article() {
+        let page = "<html><body><nav><a href=\"/a\">A</a></nav><p>Short page.</p></body></html>";
+        assert!(extract(page, "https://example.com/").is_none());
+    }
+}
@@ -4,12 +4,21 @@
 
 use std::collections::HashMap;
 use fos_dom::{Document, NodeId, DomTree};
-use fos_css::computed::{ComputedStyle, Display, SizeValue, EdgeSizes};
+use fos_css::computed::{ComputedStyle, Display, SizeValue, EdgeSizes, AccentColor, PropertyMask};
+use fos_css::Hyphens as CssHyphens;
 use fos_css::properties::LengthUnit;
-use fos_css::{Stylesheet, Selector, SelectorPart, parse_stylesheet, StyleResolver};
-use fos_layout::{LayoutTree, LayoutBoxId, layout_document};
+use fos_css::{Stylesheet, Selector, SelectorPart, parse_stylesheet, CssParser, MediaEnvironment, StyleResolver, Viewport};
+use fos_layout::{LayoutTree, LayoutBoxId, Rect, layout_document_in};
 use fos_render::{Canvas, Color, TextRenderer, css_color_to_render};
-use fos_text::{FontId, LineBreaker};
+use fos_render::{FormControlKind, FormControlState, FormControlTheme, paint_form_control};
+use fos_text::{FontId, LineBreaker, Hyphens};
+use fos_text::layout::{ParagraphStyle, HYPHEN};
+use fos_devtools::{TraceKind, Tracer};
+use fos_dom::url::Url;
+use fos_security::{ContentBlocking, ContentCategory};
+use fos_a11y::TextScalingSettings;
+use crate::history_store::VisitedLinks;
+use crate::spellcheck::{SpellChecker, spellcheck_enabled};
 
 /// A clickable link region in the rendered page
 #[derive(Debug, Clone)]
@@ -24,6 +33,8 @@ pub struct LinkRegion {
     pub height: f32,
     /// Target URL
     pub href: String,
+    /// `target` attribute: the frame to open the link in
+    pub target: String,
 }
 
 /// An anchor position (element with id attribute) for in-page navigation
@@ -35,6 +46,34 @@ pub struct AnchorPosition {
     pub y: f32,
 }
 
+/// Placeholder painted where content blocking kept a third-party frame
+/// from loading; clicking it unblocks frames on the page's site
+#[derive(Debug, Clone)]
+pub struct BlockedFrame {
+    /// Bounding box x
+    pub x: f32,
+    /// Bounding box y
+    pub y: f32,
+    /// Width
+    pub width: f32,
+    /// Height
+    pub height: f32,
+    /// URL the frame would have loaded
+    pub url: String,
+}
+
+/// Rendered content of a child frame, composited where its iframe is
+/// laid out
+#[derive(Debug, Clone)]
+pub struct FrameContent {
+    /// Pixel buffer (RGBA)
+    pub pixels: Vec<u8>,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+}
+
 /// Rendered page with pixel buffer
 pub struct RenderedPage {
     /// Pixel buffer (RGBA)
@@ -49,18 +88,83 @@ pub struct RenderedPage {
     pub links: Vec<LinkRegion>,
     /// Anchor positions for in-page navigation
     pub anchors: Vec<AnchorPosition>,
+    /// Placeholders of blocked third-party frames
+    pub blocked_frames: Vec<BlockedFrame>,
+}
+
+/// Text drawn on a printed page, so the output can carry it as text
+#[derive(Debug, Clone)]
+pub struct TextRun {
+    /// Left edge in CSS pixels from the page's left
+    pub x: f32,
+    /// Baseline in CSS pixels from the page's top
+    pub baseline: f32,
+    /// Font size in CSS pixels
+    pub font_size: f32,
+    /// Painted width in CSS pixels
+    pub width: f32,
+    pub text: String,
+}
+
+/// One page of a document laid out for print
+pub struct PrintedPage {
+    /// Pixel buffer (RGBA)
+    pub pixels: Vec<u8>,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Text painted on the page
+    pub text: Vec<TextRun>,
+    /// Links on the page, in CSS pixels from its top left
+    pub links: Vec<LinkRegion>,
 }
 
+/// Most pages one document prints to
+pub const MAX_PRINT_PAGES: usize = 1000;
+
 /// Page renderer - integrates HTML, CSS, layout, and painting
 pub struct PageRenderer {
-    /// Viewport width
+    /// Viewport width in device pixels
     viewport_width: u32,
-    /// Viewport height
+    /// Viewport height in device pixels
     viewport_height: u32,
+    /// Device pixels per CSS pixel of the display, before page zoom
+    device_pixel_ratio: f32,
+    /// Page zoom and text-only zoom
+    text_scaling: TextScalingSettings,
+    /// Layout viewport for viewport units, including browser UI state
+    viewport: Viewport,
     /// Text renderer with font support
     text_renderer: TextRenderer,
     /// Default font ID for text rendering
     default_font: Option<FontId>,
+    /// User prefers dark colors, for pages offering both color schemes
+    prefers_dark: bool,
+    /// Data saver is on, matching `prefers-reduced-data: reduce`
+    prefers_reduced_data: bool,
+    /// Decides which third-party frames paint as placeholders
+    content_blocking: ContentBlocking,
+    /// URL of the page being rendered, the initiator of its frames
+    page_url: String,
+    /// Placeholders painted so far
+    blocked_frames: Vec<BlockedFrame>,
+    /// Content of the page's child frames, by iframe in document order
+    frame_contents: Vec<Option<FrameContent>>,
+    /// Position of each iframe of the document being painted
+    frame_indices: HashMap<NodeId, usize>,
+    /// Browsing history links are matched against for `:visited`
+    visited_links: VisitedLinks,
+    /// Checks the text of editable content
+    spellchecker: SpellChecker,
+    /// Page height in CSS pixels while laying out for print
+    page_height: Option<f32>,
+    /// Scroll offset of the paint in progress, in CSS pixels
+    paint_scroll: f32,
+    /// Bottom of the content painted last, in CSS pixels
+    painted_height: f32,
+    /// Text painted so far, collected while printing
+    text_runs: Option<Vec<TextRun>>,
 }
 
 impl PageRenderer {
@@ -78,15 +182,139 @@ impl PageRenderer {
         Self {
             viewport_width,
             viewport_height,
+            device_pixel_ratio: 1.0,
+            text_scaling: TextScalingSettings::default(),
+            viewport: Viewport::new(viewport_width as f32, viewport_height as f32),
             text_renderer,
             default_font,
+            prefers_dark: false,
+            prefers_reduced_data: false,
+            content_blocking: ContentBlocking::new(),
+            page_url: String::new(),
+            blocked_frames: Vec::new(),
+            frame_contents: Vec::new(),
+            frame_indices: HashMap::new(),
+            visited_links: VisitedLinks::default(),
+            spellchecker: SpellChecker::new(),
+            page_height: None,
+            paint_scroll: 0.0,
+            painted_height: 0.0,
+            text_runs: None,
         }
     }
     
-    /// Set viewport size
+    /// Set viewport size in device pixels
     pub fn set_viewport(&mut self, width: u32, height: u32) {
         self.viewport_width = width;
         self.viewport_height = height;
+        self.resize_layout_viewport();
+    }
+    
+    /// Viewport size in device pixels
+    pub fn viewport_size(&self) -> (u32, u32) {
+        (self.viewport_width, self.viewport_height)
+    }
+    
+    /// Set the display's device pixels per CSS pixel; returns whether to re-render
+    pub fn set_device_pixel_ratio(&mut self, ratio: f32) -> bool {
+        let ratio = if ratio > 0.0 { ratio } else { 1.0 };
+        if ratio == self.device_pixel_ratio {
+            return false;
+        }
+        self.device_pixel_ratio = ratio;
+        self.resize_layout_viewport();
+        true
+    }
+    
+    /// Set page zoom and text-only zoom; returns whether to re-render
+    pub fn set_text_scaling(&mut self, settings: TextScalingSettings) -> bool {
+        let current = &self.text_scaling;
+        let changed = settings.page_zoom.factor != current.page_zoom.factor
+            || settings.text_zoom.factor != current.text_zoom.factor
+            || settings.minimum_font_size != current.minimum_font_size;
+        self.text_scaling = settings;
+        self.resize_layout_viewport();
+        changed
+    }
+    
+    pub fn text_scaling(&self) -> &TextScalingSettings {
+        &self.text_scaling
+    }
+    
+    /// Device pixels per CSS pixel, including page zoom
+    pub fn css_pixel_ratio(&self) -> f32 {
+        self.device_pixel_ratio * self.text_scaling.page_zoom.factor as f32
+    }
+    
+    /// Lay pages out in CSS pixels, so zooming in narrows the viewport
+    fn resize_layout_viewport(&mut self) {
+        let ratio = self.css_pixel_ratio();
+        self.viewport.resize(self.viewport_width as f32 / ratio, self.viewport_height as f32 / ratio);
+    }
+    
+    /// Font size after text-only zoom and the minimum font size
+    fn text_size(&self, size: f32) -> f32 {
+        self.text_scaling.apply_text_scale(size as f64) as f32
+    }
+    
+    /// Follow the user's `prefers-color-scheme` for pages that support both schemes
+    pub fn set_prefers_dark(&mut self, dark: bool) {
+        self.prefers_dark = dark;
+    }
+    
+    /// Follow the data saver preference in `prefers-reduced-data` queries
+    pub fn set_prefers_reduced_data(&mut self, reduce: bool) {
+        self.prefers_reduced_data = reduce;
+    }
+    
+    /// Follow the third-party content blocking settings
+    pub fn set_content_blocking(&mut self, blocking: ContentBlocking) {
+        self.content_blocking = blocking;
+    }
+    
+    /// Match `:visited` against the browsing history
+    pub fn set_visited_links(&mut self, visited: VisitedLinks) {
+        self.visited_links = visited;
+    }
+    
+    /// Underline misspelled words in editable content
+    pub fn set_spellchecker(&mut self, spellchecker: SpellChecker) {
+        self.spellchecker = spellchecker;
+    }
+    
+    /// Paint child frames in place of their iframes' fallback content,
+    /// by iframe in document order
+    pub fn set_frame_contents(&mut self, contents: Vec<Option<FrameContent>>) {
+        self.frame_contents = contents;
+    }
+    
+    /// Environment `@media` rules are evaluated against
+    fn media_environment(&self) -> MediaEnvironment {
+        let (width, height) = self.viewport.large();
+        MediaEnvironment {
+            prefers_dark: self.prefers_dark,
+            reduced_data: self.prefers_reduced_data,
+            resolution: self.css_pixel_ratio(),
+            print: self.page_height.is_some(),
+            ..MediaEnvironment::new(width, height)
+        }
+    }
+    
+    /// Set the height of retractable browser UI (toolbars) over the page
+    pub fn set_browser_chrome_inset(&mut self, inset: f32) {
+        self.viewport.chrome_inset = inset.max(0.0);
+    }
+    
+    /// Show or hide retractable browser UI; returns whether to re-render
+    pub fn set_browser_chrome_visible(&mut self, visible: bool) -> bool {
+        let change = self.viewport.set_chrome_visible(visible);
+        log::debug!("Browser chrome visible={}: {:?}", visible, change);
+        change.needs_relayout()
+    }
+    
+    /// Set the pinch zoom scale; returns whether to re-render
+    pub fn set_pinch_zoom(&mut self, scale: f32) -> bool {
+        self.viewport.set_pinch_zoom(scale).needs_relayout()
     }
     
     /// Measure text width using the text renderer
@@ -102,27 +330,64 @@ impl PageRenderer {
     
     /// Render HTML to pixels with scroll offset
     pub fn render_html(&mut self, html: &str, base_url: &str, scroll_offset: f32) -> Option<RenderedPage> {
+        self.render_html_traced(html, base_url, scroll_offset, &mut Tracer::new())
+    }
+    
+    /// Render HTML, recording each pipeline stage into `tracer`
+    ///
+    /// The scroll offset and the positions in the result are in device
+    /// pixels; the page itself is laid out and painted in CSS pixels.
+    pub fn render_html_traced(
+        &mut self,
+        html: &str,
+        base_url: &str,
+        scroll_offset: f32,
+        tracer: &mut Tracer,
+    ) -> Option<RenderedPage> {
+        self.page_url = base_url.to_string();
+        let ratio = self.css_pixel_ratio();
+        let scroll_offset = scroll_offset / ratio;
+        
         // 1. Parse HTML into DOM
-        let document = fos_html::parse_with_url(html, base_url);
+        let document = tracer.scope(TraceKind::Other("ParseHTML".into()), |_| {
+            fos_html::parse_with_url(html, base_url)
+        });
         
         // 2. Compute styles for all elements
-        let styles = self.compute_styles(&document);
+        let styles = tracer.scope(TraceKind::Style, |_| self.compute_styles(&document));
         
         // 3. Layout the document
-        let layout_tree = layout_document(
-            &document,
-            &styles,
-            self.viewport_width as f32,
-            self.viewport_height as f32,
-        );
+        let layout_tree = tracer.scope(TraceKind::Layout, |_| {
+            layout_document_in(&document, &styles, &self.viewport)
+        });
         
         // 4. Paint to canvas with scroll offset, collecting link regions and anchors
         let mut links = Vec::new();
         let mut anchors = Vec::new();
-        let pixels = self.paint(&document, &styles, &layout_tree, scroll_offset, &mut links, &mut anchors)?;
+        let pixels = tracer.scope(TraceKind::Paint, |_| {
+            self.paint(&document, &styles, &layout_tree, scroll_offset, &mut links, &mut anchors)
+        })?;
         
         // Calculate content height
-        let content_height = self.calculate_content_height(&layout_tree);
+        let content_height = self.calculate_content_height(&layout_tree) * ratio;
+        
+        // Report positions in device pixels, like the pixel buffer
+        for link in &mut links {
+            link.x *= ratio;
+            link.y *= ratio;
+            link.width *= ratio;
+            link.height *= ratio;
+        }
+        for anchor in &mut anchors {
+            anchor.y *= ratio;
+        }
+        let mut blocked_frames = std::mem::take(&mut self.blocked_frames);
+        for frame in &mut blocked_frames {
+            frame.x *= ratio;
+            frame.y *= ratio;
+            frame.width *= ratio;
+            frame.height *= ratio;
+        }
         
         Some(RenderedPage {
             pixels,
@@ -131,9 +396,72 @@ impl PageRenderer {
             content_height,
             links,
             anchors,
+            blocked_frames,
         })
     }
     
+    /// Lay a document out for print and render each of its pages
+    ///
+    /// Pages are the size of the viewport. `@media print` rules apply, and
+    /// lines that would straddle a page break move to the next page.
+    pub fn render_pages(&mut self, html: &str, base_url: &str) -> Vec<PrintedPage> {
+        self.page_url = base_url.to_string();
+        let page_height = self.viewport_height as f32 / self.css_pixel_ratio();
+        self.page_height = Some(page_height);
+        
+        let document = fos_html::parse_with_url(html, base_url);
+        let styles = self.compute_styles(&document);
+        let layout_tree = layout_document_in(&document, &styles, &self.viewport);
+        
+        let mut pages = Vec::new();
+        while pages.len() < MAX_PRINT_PAGES {
+            let scroll = pages.len() as f32 * page_height;
+            let mut links = Vec::new();
+            self.text_runs = Some(Vec::new());
+            let Some(pixels) = self.paint(&document, &styles, &layout_tree, scroll, &mut links, &mut Vec::new()) else {
+                break;
+            };
+            self.blocked_frames.clear();
+            
+            let on_page = |baseline: f32| baseline > 0.0 && baseline <= page_height;
+            let text = self.text_runs.take().unwrap_or_default().into_iter()
+                .filter(|run| on_page(run.baseline))
+                .collect();
+            links.retain(|link| on_page(link.y + link.height));
+            pages.push(PrintedPage {
+                pixels,
+                width: self.viewport_width,
+                height: self.viewport_height,
+                text,
+                links,
+            });
+            
+            if self.painted_height <= pages.len() as f32 * page_height {
+                break;
+            }
+        }
+        
+        self.page_height = None;
+        pages
+    }
+    
+    /// While printing, push a line that would straddle a page break down to
+    /// the top of the next page
+    fn break_line(&self, y_cursor: &mut f32, font_size: f32) {
+        let Some(page_height) = self.page_height else { return };
+        let line_height = font_size * 1.3;
+        if line_height >= page_height {
+            return;
+        }
+        
+        // Text is drawn with its baseline at the cursor
+        let top = *y_cursor + self.paint_scroll - font_size;
+        let page_end = ((top / page_height).floor() + 1.0) * page_height;
+        if top + line_height > page_end {
+            *y_cursor += page_end - top;
+        }
+    }
+    
     /// Compute styles for all elements using CSS from document
     fn compute_styles(&self, document: &Document) -> HashMap<NodeId, ComputedStyle> {
         let mut styles = HashMap::new();
@@ -142,9 +470,9 @@ impl PageRenderer {
         // 1. Extract CSS from <style> tags in <head>
         let css_text = self.extract_css_from_document(document);
         
-        // 2. Parse CSS into stylesheet
+        // 2. Parse CSS into stylesheet, keeping matching @media blocks
         let stylesheet = if !css_text.is_empty() {
-            match parse_stylesheet(&css_text) {
+            match CssParser::new().parse_with_media(&css_text, &self.media_environment()) {
                 Ok(ss) => {
                     log::debug!("Parsed {} CSS rules from page", ss.rules.len());
                     Some(ss)
@@ -161,6 +489,11 @@ impl PageRenderer {
         // 3. Compute styles for all nodes (using old method that works)
         self.compute_styles_recursive(tree, tree.root(), &mut styles, stylesheet.as_ref());
         
+        // 4. Text-only zoom grows fonts without changing other lengths
+        for style in styles.values_mut() {
+            style.font_size = self.text_size(style.font_size);
+        }
+        
         styles
     }
     
@@ -306,13 +639,20 @@ impl PageRenderer {
             .map(|c| tree.resolve(*c))
             .collect();
         
+        let visited = self.link_visited(tree, element, tag_name);
+        
         // Check each rule in stylesheet
         for rule in &stylesheet.rules {
             for selector in &rule.selectors {
-                if self.selector_matches(selector, tag_name, element_id, &element_classes) {
+                if self.selector_matches(selector, tag_name, element_id, &element_classes, visited) {
+                    // `:visited` rules only set colors
+                    let visited_rule = selector.parts.iter().any(|p| matches!(p, SelectorPart::PseudoClass(c) if c == "visited"));
                     // Apply declarations from this rule
                     for decl in &rule.declarations {
-                        style.apply_declaration(decl);
+                        if visited_rule && !decl.property.applies_to_visited() {
+                            continue;
+                        }
+                        style.apply_declaration_in(decl, &self.viewport);
                     }
                 }
             }
@@ -326,6 +666,7 @@ impl PageRenderer {
         tag_name: &str,
         element_id: Option<&str>,
         classes: &[&str],
+        visited: Option<bool>,
     ) -> bool {
         // Safety check: empty selector parts shouldn't match
         if selector.parts.is_empty() {
@@ -365,6 +706,12 @@ impl PageRenderer {
                     // Only count as match if we had something before the combinator
                     break;
                 }
+                // Links match one of `:visited` and `:link`, other elements neither
+                SelectorPart::PseudoClass(c) if c == "visited" || c == "link" => {
+                    if visited != Some(c == "visited") {
+                        return false;
+                    }
+                }
                 SelectorPart::PseudoClass(_) | SelectorPart::PseudoElement(_) | SelectorPart::Attribute { .. } => {
                     // Skip pseudo-classes/elements and attribute selectors
                     // Don't count as match, but don't reject either
@@ -383,7 +730,7 @@ impl PageRenderer {
         if let Ok(ss) = parse_stylesheet(&wrapped) {
             for rule in &ss.rules {
                 for decl in &rule.declarations {
-                    style.apply_declaration(decl);
+                    style.apply_declaration_in(decl, &self.viewport);
                 }
             }
         }
@@ -401,21 +748,27 @@ impl PageRenderer {
     ) -> Option<Vec<u8>> {
         // Create canvas
         let mut canvas = Canvas::new(self.viewport_width, self.viewport_height)?;
-        
-        // Fill with white background
-        canvas.clear(Color::WHITE);
+        canvas.set_scale(self.css_pixel_ratio());
         
         // Paint using a simple DOM-based approach
         // Walk the DOM tree and paint text directly
         let tree = document.tree();
         let body = document.body();
+        self.frame_indices = iframe_elements(tree).into_iter().enumerate().map(|(i, id)| (id, i)).collect();
+        
+        // Fill with the canvas color of the root's color scheme
+        let dark = self.uses_dark_scheme(tree, document.document_element(), styles);
+        canvas.clear(FormControlTheme::for_scheme(dark).canvas);
         
         log::info!("DOM tree size: {}, body valid: {}", tree.len(), body.is_valid());
         
+        self.painted_height = 0.0;
         if body.is_valid() {
             // Apply scroll offset to starting position
+            self.paint_scroll = scroll_offset;
             let mut y_cursor = 20.0f32 - scroll_offset;
             self.paint_dom_node(&mut canvas, tree, body, styles, 8.0, &mut y_cursor, links, anchors);
+            self.painted_height = y_cursor + scroll_offset;
             
             // If no content was painted (taking scroll into account), show a message
             if y_cursor < 30.0 - scroll_offset {
@@ -445,8 +798,12 @@ impl PageRenderer {
         anchors: &mut Vec<AnchorPosition>,
     ) {
         // Use a line buffer for text accumulation (leave margin for right edge)
-        let max_width = canvas.width() as f32 - 30.0;
-        let mut line_buffer = LineBuffer::new(x_offset, max_width, 16.0);
+        let max_width = canvas.css_size().0 - 30.0;
+        let mut line_buffer = LineBuffer::new(x_offset, max_width, self.text_size(16.0));
+        line_buffer.dark = self.uses_dark_scheme(tree, node_id, styles);
+        line_buffer.accent = inherited_style(tree, node_id, styles, PropertyMask::ACCENT_COLOR)
+            .and_then(|s| accent_color(s.accent_color));
+        line_buffer.current_color = FormControlTheme::for_scheme(line_buffer.dark).canvas_text;
         self.paint_node_recursive(canvas, tree, node_id, styles, &mut line_buffer, y_cursor, links, anchors);
         
         // Flush any remaining text
@@ -487,10 +844,13 @@ impl PageRenderer {
             let text_str = text.replace('\n', " ");
             let trimmed = text_str.split_whitespace().collect::<Vec<_>>().join(" ");
             if !trimmed.is_empty() {
-                let font_size = line_buffer.current_font_size.max(14.0);
+                let font_size = line_buffer.current_font_size.max(self.text_size(14.0));
                 let text_color = line_buffer.current_color;
                 
+                line_buffer.spellcheck = self.spellchecker.is_enabled() && spellcheck_enabled(tree, node_id);
+                line_buffer.paragraph = paragraph_style(tree, node_id, styles);
                 line_buffer.add_text(&trimmed, font_size, text_color);
+                line_buffer.spellcheck = false;
             }
             return;
         }
@@ -543,12 +903,30 @@ impl PageRenderer {
                 return;
             }
             
+            // Blocked frames paint a placeholder instead of their fallback,
+            // and loaded frames their content
+            if tag == "iframe" {
+                if let Some(url) = self.blocked_frame_url(tree, element) {
+                    self.paint_blocked_frame(canvas, frame_size(tree, element), url, line_buffer, y_cursor, links);
+                    return;
+                }
+                let index = self.frame_indices.get(&node_id).copied()
+                    .filter(|&i| matches!(self.frame_contents.get(i), Some(Some(_))));
+                if let Some(index) = index {
+                    self.paint_frame(canvas, index, frame_size(tree, element), line_buffer, y_cursor, links);
+                    return;
+                }
+            }
+            
             // Save current state for restoration
             let saved_font_size = line_buffer.current_font_size;
             let saved_color = line_buffer.current_color;
             let saved_indent = line_buffer.indent_level;
             let saved_href = line_buffer.current_href.clone();
+            let saved_target = line_buffer.current_target.clone();
             let saved_list_counter = line_buffer.list_counter;
+            let saved_dark = line_buffer.dark;
+            let saved_accent = line_buffer.accent;
             
             // Increment indent for lists and blockquotes
             if tag == "ul" || tag == "ol" || tag == "blockquote" {
@@ -601,25 +979,29 @@ impl PageRenderer {
             
             // Set font size based on heading
             match tag.as_str() {
-                "h1" => line_buffer.current_font_size = 28.0,
-                "h2" => line_buffer.current_font_size = 24.0,
-                "h3" => line_buffer.current_font_size = 20.0,
-                "h4" => line_buffer.current_font_size = 18.0,
-                "h5" => line_buffer.current_font_size = 16.0,
-                "h6" => line_buffer.current_font_size = 14.0,
-                "small" => line_buffer.current_font_size = (saved_font_size * 0.8).max(12.0),
+                "h1" => line_buffer.current_font_size = self.text_size(28.0),
+                "h2" => line_buffer.current_font_size = self.text_size(24.0),
+                "h3" => line_buffer.current_font_size = self.text_size(20.0),
+                "h4" => line_buffer.current_font_size = self.text_size(18.0),
+                "h5" => line_buffer.current_font_size = self.text_size(16.0),
+                "h6" => line_buffer.current_font_size = self.text_size(14.0),
+                "small" => line_buffer.current_font_size = (saved_font_size * 0.8).max(self.text_size(12.0)),
                 _ => {}
             };
             
-            // Links get blue color and save href
+            // Links get blue color, purple once visited, and save href
             if tag == "a" {
-                line_buffer.current_color = Color::rgb(51, 102, 204); // Wikipedia link blue
-                // Extract href attribute
+                line_buffer.current_color = match self.link_visited(tree, element, &tag) {
+                    Some(true) => Color::rgb(85, 26, 139),
+                    _ => Color::rgb(51, 102, 204), // Wikipedia link blue
+                };
+                // Extract href and target attributes
+                line_buffer.current_target.clear();
                 for attr in element.attrs.iter() {
-                    let attr_name = tree.resolve(attr.name.local);
-                    if attr_name == "href" {
-                        line_buffer.current_href = Some(attr.value.to_string());
-                        break;
+                    match tree.resolve(attr.name.local) {
+                        "href" => line_buffer.current_href = Some(attr.value.to_string()),
+                        "target" => line_buffer.current_target = attr.value.trim().to_string(),
+                        _ => {}
                     }
                 }
             }
@@ -654,9 +1036,26 @@ impl PageRenderer {
                     break;
                 }
             }
-            // Recurse into children
-            for (child_id, _) in tree.children(node_id) {
-                self.paint_node_recursive(canvas, tree, child_id, styles, line_buffer, y_cursor, links, anchors);
+            
+            // color-scheme and accent-color inherit into descendants
+            if let Some(s) = style {
+                if s.property_mask.is_set(PropertyMask::COLOR_SCHEME) {
+                    line_buffer.dark = s.color_scheme.uses_dark(self.prefers_dark);
+                }
+                if s.property_mask.is_set(PropertyMask::ACCENT_COLOR) {
+                    line_buffer.accent = accent_color(s.accent_color);
+                }
+            }
+            
+            if let Some(kind) = FormControlKind::for_element(&tag, element_attr(tree, element, "type")) {
+                // Controls paint natively in place of their children
+                let control = self.form_control(tree, node_id, element, kind, style, line_buffer);
+                line_buffer.add_control(control);
+            } else {
+                // Recurse into children
+                for (child_id, _) in tree.children(node_id) {
+                    self.paint_node_recursive(canvas, tree, child_id, styles, line_buffer, y_cursor, links, anchors);
+                }
             }
             
             // Restore state
@@ -664,7 +1063,10 @@ impl PageRenderer {
             line_buffer.current_color = saved_color;
             line_buffer.indent_level = saved_indent;
             line_buffer.current_href = saved_href;
+            line_buffer.current_target = saved_target;
             line_buffer.list_counter = saved_list_counter;
+            line_buffer.dark = saved_dark;
+            line_buffer.accent = saved_accent;
             line_buffer.current_x = line_buffer.effective_start_x();
             
             // Block elements flush after and add space
@@ -685,6 +1087,261 @@ impl PageRenderer {
             }
         }
     }
+    
+    /// Whether a link's URL is in the browsing history, `None` for
+    /// elements that are not links
+    fn link_visited(&self, tree: &DomTree, element: &fos_dom::ElementData, tag: &str) -> Option<bool> {
+        if tag != "a" && tag != "area" {
+            return None;
+        }
+        let href = element_attr(tree, element, "href")?.trim();
+        let url = Url::parse(&self.page_url).ok().and_then(|base| base.join(href).ok());
+        Some(url.is_some_and(|url| self.visited_links.contains(&url.to_string())))
+    }
+    
+    /// Resolved `src` of an iframe content blocking keeps from loading
+    fn blocked_frame_url(&self, tree: &DomTree, element: &fos_dom::ElementData) -> Option<String> {
+        let src = element_attr(tree, element, "src")?.trim();
+        let url = Url::parse(&self.page_url).ok()?.join(src).ok()?.to_string();
+        self.content_blocking.should_block(&url, &self.page_url, ContentCategory::Frames).then_some(url)
+    }
+    
+    /// Paint the placeholder of a blocked frame on a line of its own, sized
+    /// like the frame, and record it for click-to-unblock
+    fn paint_blocked_frame(
+        &mut self,
+        canvas: &mut Canvas,
+        (width, height): (f32, f32),
+        url: String,
+        line_buffer: &mut LineBuffer,
+        y_cursor: &mut f32,
+        links: &mut Vec<LinkRegion>,
+    ) {
+        if !line_buffer.is_empty() {
+            line_buffer.flush(canvas, y_cursor, self, links);
+        }
+        let x = line_buffer.effective_start_x();
+        let width = width.min(line_buffer.max_width - x);
+        let height = height.max(48.0);
+        let top = *y_cursor - line_buffer.current_font_size;
+        
+        let theme = FormControlTheme::for_scheme(line_buffer.dark);
+        canvas.fill_rect(x, top, width, height, theme.button_face);
+        canvas.stroke_rect(x, top, width, height, 1.0, theme.border);
+        let font_size = 14.0;
+        let host = Url::parse(&url).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_default();
+        self.paint_text(canvas, &format!("Frame from {} blocked", host), x + 10.0, top + 22.0, theme.button_text, font_size);
+        self.paint_text(canvas, "Click to load content from this site", x + 10.0, top + 42.0, theme.accent, font_size);
+        
+        self.blocked_frames.push(BlockedFrame { x, y: top, width, height, url });
+        *y_cursor += height + 8.0;
+    }
+    
+    /// Composite a child frame on a line of its own, in the box its
+    /// iframe is laid out at
+    fn paint_frame(
+        &mut self,
+        canvas: &mut Canvas,
+        index: usize,
+        (width, height): (f32, f32),
+        line_buffer: &mut LineBuffer,
+        y_cursor: &mut f32,
+        links: &mut Vec<LinkRegion>,
+    ) {
+        if !line_buffer.is_empty() {
+            line_buffer.flush(canvas, y_cursor, self, links);
+        }
+        let x = line_buffer.effective_start_x();
+        let width = width.min(line_buffer.max_width - x);
+        let top = *y_cursor - line_buffer.current_font_size;
+        
+        if let Some(Some(content)) = self.frame_contents.get(index) {
+            // Child pixels are device pixels at the same scale
+            let scale = canvas.scale();
+            let (left, offset) = ((x * scale).round() as i64, (top * scale).round() as i64);
+            let columns = ((width * scale) as u32).min(content.width);
+            let rows = ((height * scale) as u32).min(content.height);
+            for row in 0..rows {
+                let y = offset + row as i64;
+                if y < 0 || y >= canvas.height() as i64 {
+                    continue;
+                }
+                for column in 0..columns {
+                    let start = ((row * content.width + column) * 4) as usize;
+                    let Some(pixel) = content.pixels.get(start..start + 4) else { break };
+                    if left + (column as i64) >= 0 {
+                        canvas.set_pixel((left + column as i64) as u32, y as u32, Color::rgba(pixel[0], pixel[1], pixel[2], pixel[3]));
+                    }
+                }
+            }
+        }
+        let theme = FormControlTheme::for_scheme(line_buffer.dark);
+        canvas.stroke_rect(x, top, width, height, 1.0, theme.border);
+        *y_cursor += height + 8.0;
+    }
+    
+    /// Whether a node uses dark colors under its inherited `color-scheme`
+    fn uses_dark_scheme(&self, tree: &DomTree, node_id: NodeId, styles: &HashMap<NodeId, ComputedStyle>) -> bool {
+        inherited_style(tree, node_id, styles, PropertyMask::COLOR_SCHEME)
+            .is_some_and(|s| s.color_scheme.uses_dark(self.prefers_dark))
+    }
+    
+    /// Build the inline control painted for a form element
+    fn form_control(
+        &mut self,
+        tree: &DomTree,
+        node_id: NodeId,
+        element: &fos_dom::ElementData,
+        kind: FormControlKind,
+        style: Option<&ComputedStyle>,
+        line_buffer: &LineBuffer,
+    ) -> InlineControl {
+        let attr = |name| element_attr(tree, element, name);
+        let number = |name, default: f32| {
+            attr(name).and_then(|v| v.trim().parse::<f32>().ok()).unwrap_or(default)
+        };
+        let tag = tree.resolve(element.name.local).to_ascii_lowercase();
+        let font_size = line_buffer.current_font_size;
+        
+        let mut theme = FormControlTheme::for_scheme(line_buffer.dark);
+        if let Some(accent) = line_buffer.accent {
+            theme = theme.with_accent(accent);
+        }
+        
+        let fraction = |value: f32, min: f32, max: f32| if max > min { (value - min) / (max - min) } else { 0.0 };
+        let state = FormControlState {
+            checked: attr("checked").is_some(),
+            disabled: attr("disabled").is_some(),
+            focused: attr("autofocus").is_some(),
+            value: match kind {
+                FormControlKind::Range => {
+                    let (min, max) = (number("min", 0.0), number("max", 100.0));
+                    fraction(number("value", (min + max) / 2.0), min, max)
+                }
+                FormControlKind::Progress => {
+                    let (min, max) = (number("min", 0.0), number("max", 1.0));
+                    fraction(number("value", min), min, max)
+                }
+                _ => 0.0,
+            },
+        };
+        
+        let native = style.is_none_or(|s| s.appearance.is_native());
+        let mut placeholder = false;
+        let mut label_color = match kind {
+            _ if !native => line_buffer.current_color,
+            FormControlKind::Button => theme.button_text,
+            _ => theme.field_text,
+        };
+        let label = match kind {
+            FormControlKind::Button if tag == "input" => match attr("value") {
+                Some(value) => value.to_string(),
+                None => match attr("type").map(str::to_ascii_lowercase).as_deref() {
+                    Some("submit") => "Submit".to_string(),
+                    Some("reset") => "Reset".to_string(),
+                    _ => String::new(),
+                },
+            },
+            FormControlKind::Button => text_content(tree, node_id),
+            FormControlKind::Select => selected_option_text(tree, node_id),
+            FormControlKind::TextField if tag == "textarea" => text_content(tree, node_id),
+            FormControlKind::TextField => match attr("value").filter(|v| !v.is_empty()) {
+                Some(value) if attr("type").is_some_and(|t| t.eq_ignore_ascii_case("password")) => {
+                    "\u{2022}".repeat(value.chars().count())
+                }
+                Some(value) => value.to_string(),
+                None => {
+                    // Placeholders use the muted border tone
+                    label_color = theme.border;
+                    placeholder = true;
+                    attr("placeholder").unwrap_or_default().to_string()
+                }
+            },
+            _ => String::new(),
+        };
+        
+        let (mut width, mut height) = kind.default_size(font_size);
+        if tag == "textarea" {
+            width = (number("cols", 20.0) * font_size * 0.5).round() + CONTROL_PADDING * 2.0;
+            height = (number("rows", 2.0) * font_size * 1.2).round() + CONTROL_PADDING;
+        }
+        if matches!(kind, FormControlKind::Button | FormControlKind::Select) && !label.is_empty() {
+            let chevron = if kind == FormControlKind::Select { 20.0 } else { 0.0 };
+            width = width.max(self.measure_text(&label, font_size) + CONTROL_PADDING * 2.0 + chevron);
+        }
+        if let Some(s) = style {
+            if let SizeValue::Length(w, LengthUnit::Px) = s.width {
+                width = w;
+            }
+            if let SizeValue::Length(h, LengthUnit::Px) = s.height {
+                height = h;
+            }
+        }
+        
+        let spellcheck = kind == FormControlKind::TextField
+            && !placeholder
+            && self.spellchecker.is_enabled()
+            && spellcheck_enabled(tree, node_id);
+        
+        InlineControl { kind, state, theme, width, height, label, label_color, native, spellcheck }
+    }
+    
+    /// Paint a form control whose line has its baseline at `baseline`
+    fn paint_inline_control(&mut self, canvas: &mut Canvas, control: &InlineControl, x: f32, baseline: f32, font_size: f32) {
+        let top = baseline - control.height + font_size * 0.25;
+        if control.native {
+            let rect = Rect::new(x, top, control.width, control.height);
+            paint_form_control(canvas, control.kind, &rect, &control.state, &control.theme);
+        }
+        
+        if !control.label.is_empty() {
+            let text_x = if control.kind == FormControlKind::Button {
+                x + (control.width - self.measure_text(&control.label, font_size)).max(0.0) / 2.0
+            } else {
+                x + CONTROL_PADDING
+            };
+            let text_baseline = top + (control.height + font_size * 0.7) / 2.0;
+            self.paint_text(canvas, &control.label, text_x, text_baseline, control.label_color, font_size);
+            if control.spellcheck {
+                self.paint_misspellings(canvas, &control.label, text_x, text_baseline, font_size);
+            }
+        }
+    }
+    
+    /// Wavy underline under each misspelled word of text painted at `x`
+    fn paint_misspellings(&mut self, canvas: &mut Canvas, text: &str, x: f32, baseline: f32, font_size: f32) {
+        for misspelling in self.spellchecker.check(text) {
+            let start = x + self.measure_text(&text[..misspelling.range.start], font_size);
+            let width = self.measure_text(&misspelling.word, font_size);
+            paint_wavy_line(canvas, start, baseline + 2.0, width, SPELLING_ERROR_COLOR);
+        }
+    }
+}
+
+/// Iframes of a document in document order, leaving out those in the
+/// fallback content of another iframe
+pub(crate) fn iframe_elements(tree: &DomTree) -> Vec<NodeId> {
+    fn collect(tree: &DomTree, node_id: NodeId, out: &mut Vec<NodeId>) {
+        for (child_id, child) in tree.children(node_id) {
+            match child.as_element() {
+                Some(element) if tree.resolve(element.name.local).eq_ignore_ascii_case("iframe") => out.push(child_id),
+                _ => collect(tree, child_id, out),
+            }
+        }
+    }
+    let mut frames = Vec::new();
+    collect(tree, tree.root(), &mut frames);
+    frames
+}
+
+/// Size of an iframe from its attributes, 300x150 by default
+pub(crate) fn frame_size(tree: &DomTree, element: &fos_dom::ElementData) -> (f32, f32) {
+    let size = |name, default: f32| {
+        element_attr(tree, element, name)
+            .and_then(|v| v.trim().trim_end_matches("px").parse::<f32>().ok())
+            .unwrap_or(default)
+    };
+    (size("width", 300.0), size("height", 150.0))
 }
 
 /// Parse color from inline style attribute
@@ -767,6 +1424,121 @@ fn parse_css_color(value: &str) -> Option<Color> {
     None
 }
 
+/// Gap after an inline form control
+const CONTROL_SPACING: f32 = 4.0;
+
+/// Inner padding of text in form controls
+const CONTROL_PADDING: f32 = 4.0;
+
+/// Underline color of misspelled words
+const SPELLING_ERROR_COLOR: Color = Color::rgb(220, 38, 38);
+
+/// A 1px zigzag two pixels tall, `width` long
+fn paint_wavy_line(canvas: &mut Canvas, x: f32, y: f32, width: f32, color: Color) {
+    let mut dx = 0.0;
+    while dx < width {
+        let offset = match dx as u32 % 4 {
+            0 => 0.0,
+            2 => 2.0,
+            _ => 1.0,
+        };
+        canvas.fill_rect(x + dx, y + offset, 1.0, 1.0, color);
+        dx += 1.0;
+    }
+}
+
+/// Nearest style on the node or its ancestors that sets `property`
+fn inherited_style<'a>(
+    tree: &DomTree,
+    node_id: NodeId,
+    styles: &'a HashMap<NodeId, ComputedStyle>,
+    property: u64,
+) -> Option<&'a ComputedStyle> {
+    let mut id = node_id;
+    while id.is_valid() {
+        if let Some(style) = styles.get(&id).filter(|s| s.property_mask.is_set(property)) {
+            return Some(style);
+        }
+        id = tree.get(id)?.parent;
+    }
+    None
+}
+
+/// Paragraph style of text under a node: the inherited `hyphens` property
+/// and the content language from the nearest `lang` attribute
+fn paragraph_style(tree: &DomTree, node_id: NodeId, styles: &HashMap<NodeId, ComputedStyle>) -> ParagraphStyle {
+    let hyphens = match inherited_style(tree, node_id, styles, PropertyMask::HYPHENS).map(|s| s.hyphens) {
+        Some(CssHyphens::None) => Hyphens::None,
+        Some(CssHyphens::Auto) => Hyphens::Auto,
+        Some(CssHyphens::Manual) | None => Hyphens::Manual,
+    };
+    let mut lang = None;
+    let mut id = node_id;
+    while id.is_valid() && lang.is_none() {
+        let Some(node) = tree.get(id) else { break };
+        lang = node.as_element().and_then(|e| element_attr(tree, e, "lang")).map(str::to_string);
+        id = node.parent;
+    }
+    ParagraphStyle { hyphens, lang, ..ParagraphStyle::default() }
+}
+
+/// Author accent, or `None` for the platform one
+fn accent_color(accent: AccentColor) -> Option<Color> {
+    match accent {
+        AccentColor::Auto => None,
+        AccentColor::Color(c) => Some(css_color_to_render(&c)),
+    }
+}
+
+/// Value of an element attribute
+fn element_attr<'a>(tree: &DomTree, element: &'a fos_dom::ElementData, name: &str) -> Option<&'a str> {
+    element.attrs.iter()
+        .find(|a| tree.resolve(a.name.local).eq_ignore_ascii_case(name))
+        .map(|a| a.value.as_str())
+}
+
+/// Whitespace-collapsed text of a node's descendants
+fn text_content(tree: &DomTree, node_id: NodeId) -> String {
+    fn collect(tree: &DomTree, node_id: NodeId, out: &mut String) {
+        for (child_id, child) in tree.children(node_id) {
+            if let Some(text) = child.as_text() {
+                out.push_str(text);
+                out.push(' ');
+            } else {
+                collect(tree, child_id, out);
+            }
+        }
+    }
+    let mut text = String::new();
+    collect(tree, node_id, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Text of the selected `<option>` of a `<select>`, or of its first option
+fn selected_option_text(tree: &DomTree, select: NodeId) -> String {
+    fn options(tree: &DomTree, node_id: NodeId, out: &mut Vec<NodeId>) {
+        for (child_id, child) in tree.children(node_id) {
+            if let Some(element) = child.as_element() {
+                if tree.resolve(element.name.local).eq_ignore_ascii_case("option") {
+                    out.push(child_id);
+                } else {
+                    options(tree, child_id, out);
+                }
+            }
+        }
+    }
+    let mut all = Vec::new();
+    options(tree, select, &mut all);
+    let selected = all.iter().copied().find(|&id| {
+        tree.get(id)
+            .and_then(|n| n.as_element())
+            .is_some_and(|e| element_attr(tree, e, "selected").is_some())
+    });
+    selected.or(all.first().copied())
+        .map(|id| text_content(tree, id))
+        .unwrap_or_default()
+}
+
 /// Line buffer for accumulating inline text
 struct LineBuffer {
     segments: Vec<TextSegment>,
@@ -779,8 +1551,18 @@ struct LineBuffer {
     indent_level: u32,
     /// Current link href (if inside an <a> tag)
     current_href: Option<String>,
+    /// `target` of the current link
+    current_target: String,
     /// Current list counter for <ol> (0 means unordered list or not in list)
     list_counter: u32,
+    /// Used color scheme is dark
+    dark: bool,
+    /// Author `accent-color`, if any
+    accent: Option<Color>,
+    /// Text being added is spellchecked
+    spellcheck: bool,
+    /// Hyphenation and content language of the text being added
+    paragraph: ParagraphStyle,
 }
 
 
@@ -792,6 +1574,28 @@ struct TextSegment {
     x: f32,
     /// Link href if this is a link
     href: Option<String>,
+    /// `target` of the link
+    target: String,
+    /// Form control painted instead of text
+    control: Option<InlineControl>,
+    /// Misspelled words are underlined
+    spellcheck: bool,
+}
+
+/// Form control placed in a line of text
+struct InlineControl {
+    kind: FormControlKind,
+    state: FormControlState,
+    theme: FormControlTheme,
+    width: f32,
+    height: f32,
+    /// Text inside the control: button label, field value or selected option
+    label: String,
+    label_color: Color,
+    /// Painted natively; `appearance: none` leaves just the label
+    native: bool,
+    /// Misspelled words in the label are underlined
+    spellcheck: bool,
 }
 
 impl LineBuffer {
@@ -805,7 +1609,12 @@ impl LineBuffer {
             current_color: Color::BLACK,
             indent_level: 0,
             current_href: None,
+            current_target: String::new(),
             list_counter: 0,
+            dark: false,
+            accent: None,
+            spellcheck: false,
+            paragraph: ParagraphStyle::default(),
         }
     }
     
@@ -833,18 +1642,27 @@ impl LineBuffer {
         
         // Use LineBreaker for proper Unicode-aware line breaking
         let available_width = wrap_width - self.current_x;
-        let lines = LineBreaker::break_lines(text, available_width.max(wrap_width * 0.5), |s| measure(s, font_size));
+        let lines = LineBreaker::break_lines_hyphenated(
+            text,
+            available_width.max(wrap_width * 0.5),
+            self.paragraph.hyphens,
+            self.paragraph.lang.as_deref(),
+            |s| measure(s, font_size),
+        );
         
-        for (i, &(start, end)) in lines.iter().enumerate() {
-            let line_text = &text[start..end];
-            let trimmed = line_text.trim_end();
+        for (i, line) in lines.iter().enumerate() {
+            // Soft hyphens only show as the hyphen of a hyphenated line
+            let mut trimmed = text[line.start..line.end].trim_end().replace('\u{AD}', "");
             
             if trimmed.is_empty() {
                 continue;
             }
+            if line.hyphenated {
+                trimmed.push_str(HYPHEN);
+            }
             
             // Check if this line needs wrapping from current position
-            let line_width = measure(trimmed, font_size);
+            let line_width = measure(&trimmed, font_size);
             
             if i > 0 || (self.current_x + line_width > wrap_width && self.current_x > effective_start) {
                 // Need to wrap - start new line
@@ -855,6 +1673,9 @@ impl LineBuffer {
                         color,
                         x: self.current_x,
                         href: None,
+                        target: String::new(),
+                        control: None,
+                        spellcheck: false,
                     });
                 }
                 self.current_x = effective_start;
@@ -867,12 +1688,51 @@ impl LineBuffer {
                 color,
                 x: self.current_x,
                 href: self.current_href.clone(),
+                target: self.current_target.clone(),
+                control: None,
+                spellcheck: self.spellcheck,
             });
             
             self.current_x += line_width + space_width;
         }
     }
     
+    /// Place a form control after the current text, wrapping if it doesn't fit
+    fn add_control(&mut self, control: InlineControl) {
+        let effective_start = self.effective_start_x();
+        let wrap_width = self.max_width - 15.0;
+        if self.current_x < effective_start {
+            self.current_x = effective_start;
+        }
+        
+        if self.current_x + control.width > wrap_width && self.current_x > effective_start {
+            self.segments.push(TextSegment {
+                text: "\n".to_string(),
+                font_size: self.current_font_size,
+                color: self.current_color,
+                x: self.current_x,
+                href: None,
+                target: String::new(),
+                control: None,
+                spellcheck: false,
+            });
+            self.current_x = effective_start;
+        }
+        
+        let width = control.width;
+        self.segments.push(TextSegment {
+            text: String::new(),
+            font_size: self.current_font_size,
+            color: self.current_color,
+            x: self.current_x,
+            href: None,
+            target: String::new(),
+            control: Some(control),
+            spellcheck: false,
+        });
+        self.current_x += width + CONTROL_SPACING;
+    }
+    
     // Keep fallback without measure function for backwards compatibility
     fn add_text(&mut self, text: &str, font_size: f32, color: Color) {
         // Fallback using approximate character width
@@ -888,15 +1748,27 @@ impl LineBuffer {
         let effective_start = self.effective_start_x();
         let mut x = effective_start;
         let line_height = self.current_font_size * 1.3;
+        let tallest = self.segments.iter().map(|s| s.font_size).fold(self.current_font_size, f32::max);
+        renderer.break_line(y_cursor, tallest);
         
         for segment in &self.segments {
             if segment.text == "\n" {
                 *y_cursor += line_height;
+                renderer.break_line(y_cursor, tallest);
                 x = effective_start;
                 continue;
             }
             
+            if let Some(ref control) = segment.control {
+                renderer.paint_inline_control(canvas, control, x, *y_cursor, segment.font_size);
+                x += control.width + CONTROL_SPACING;
+                continue;
+            }
+            
             renderer.paint_text(canvas, &segment.text, x, *y_cursor, segment.color, segment.font_size);
+            if segment.spellcheck {
+                renderer.paint_misspellings(canvas, &segment.text, x, *y_cursor, segment.font_size);
+            }
             
             // Use proper text measurement instead of character counting
             let text_width = renderer.measure_text(&segment.text, segment.font_size);
@@ -911,6 +1783,7 @@ impl LineBuffer {
                     width: text_width,
                     height: char_height,
                     href: href.clone(),
+                    target: segment.target.clone(),
                 });
             }
             
@@ -1033,6 +1906,13 @@ impl PageRenderer {
         color: Color,
         font_size: f32,
     ) {
+        if self.text_runs.is_some() && !text.trim().is_empty() {
+            let width = self.measure_text(text, font_size);
+            if let Some(runs) = &mut self.text_runs {
+                runs.push(TextRun { x, baseline: y, font_size, width, text: text.to_string() });
+            }
+        }
+        
         // Use TextRenderer if we have a font
         if let Some(font_id) = self.default_font {
             // Use the proper font rendering
@@ -1053,7 +1933,7 @@ impl PageRenderer {
                     x_pos += char_width * 0.8;
                     continue;
                 }
-                if x_pos > canvas.width() as f32 {
+                if x_pos > canvas.css_size().0 {
                     break;
                 }
                 
@@ -1088,7 +1968,7 @@ impl PageRenderer {
                     + root_box.dimensions.margin.top
                     + root_box.dimensions.margin.bottom
             })
-            .unwrap_or(self.viewport_height as f32)
+            .unwrap_or(self.viewport_height as f32 / self.css_pixel_ratio())
     }
 }
 
@@ -0,0 +1,446 @@
+//! Reporting API
+//!
+//! Per-document report queues, ReportingObserver, and the deprecation,
+//! intervention, crash and CSP violation reports the engine generates.
+//! Endpoint headers are parsed and reports uploaded through fos-net.
+
+use fos_engine::url::Url;
+use fos_net::reporting::{
+    parse_report_to, parse_reporting_endpoints, DeliveryOutcome, QueuedReport, ReportTransport, ReportUploader,
+    ReportValue,
+};
+use fos_security::CspViolation;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Instant;
+
+/// Reports kept per document for `buffered` observers
+const REPORT_BUFFER_LIMIT: usize = 100;
+/// Endpoint that receives reports without an explicit destination
+pub const DEFAULT_ENDPOINT: &str = "default";
+
+/// Report type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportType {
+    Deprecation,
+    Intervention,
+    Crash,
+    CspViolation,
+}
+
+impl ReportType {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "deprecation" => Some(Self::Deprecation),
+            "intervention" => Some(Self::Intervention),
+            "crash" => Some(Self::Crash),
+            "csp-violation" => Some(Self::CspViolation),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Deprecation => "deprecation",
+            Self::Intervention => "intervention",
+            Self::Crash => "crash",
+            Self::CspViolation => "csp-violation",
+        }
+    }
+
+    /// Whether ReportingObserver sees this type; crash reports outlive the page
+    pub fn is_observable(&self) -> bool {
+        !matches!(self, Self::Crash)
+    }
+}
+
+/// Script location a report points at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub source_file: String,
+    pub line_number: u32,
+    pub column_number: u32,
+}
+
+/// Report body
+#[derive(Debug, Clone)]
+pub enum ReportBody {
+    Deprecation {
+        id: String,
+        message: String,
+        /// Date the feature goes away, if planned
+        anticipated_removal: Option<String>,
+        location: Option<SourceLocation>,
+    },
+    Intervention {
+        id: String,
+        message: String,
+        location: Option<SourceLocation>,
+    },
+    Crash {
+        /// "oom" or "unresponsive"
+        reason: Option<String>,
+    },
+    CspViolation(CspViolation),
+}
+
+impl ReportBody {
+    pub fn report_type(&self) -> ReportType {
+        match self {
+            Self::Deprecation { .. } => ReportType::Deprecation,
+            Self::Intervention { .. } => ReportType::Intervention,
+            Self::Crash { .. } => ReportType::Crash,
+            Self::CspViolation(_) => ReportType::CspViolation,
+        }
+    }
+
+    /// Serialized members, as `ReportBody.toJSON()` and uploads see them
+    pub fn fields(&self) -> Vec<(String, ReportValue)> {
+        fn str(s: &str) -> ReportValue { ReportValue::Str(s.to_string()) }
+        fn location(fields: &mut Vec<(String, ReportValue)>, location: &Option<SourceLocation>) {
+            let (file, line, column) = match location {
+                Some(l) => (str(&l.source_file), ReportValue::Int(l.line_number as i64), ReportValue::Int(l.column_number as i64)),
+                None => (ReportValue::Null, ReportValue::Null, ReportValue::Null),
+            };
+            fields.push(("sourceFile".into(), file));
+            fields.push(("lineNumber".into(), line));
+            fields.push(("columnNumber".into(), column));
+        }
+
+        let mut fields = Vec::new();
+        match self {
+            Self::Deprecation { id, message, anticipated_removal, location: loc } => {
+                fields.push(("id".into(), str(id)));
+                fields.push(("anticipatedRemoval".into(), anticipated_removal.as_deref().map_or(ReportValue::Null, str)));
+                fields.push(("message".into(), str(message)));
+                location(&mut fields, loc);
+            }
+            Self::Intervention { id, message, location: loc } => {
+                fields.push(("id".into(), str(id)));
+                fields.push(("message".into(), str(message)));
+                location(&mut fields, loc);
+            }
+            Self::Crash { reason } => {
+                fields.push(("reason".into(), reason.as_deref().map_or(ReportValue::Null, str)));
+            }
+            Self::CspViolation(v) => {
+                fields.push(("documentURL".into(), str(&v.document_uri)));
+                fields.push(("blockedURL".into(), str(&v.blocked_uri)));
+                fields.push(("effectiveDirective".into(), str(&v.effective_directive)));
+                fields.push(("originalPolicy".into(), str(&v.original_policy)));
+                fields.push(("disposition".into(), str("enforce")));
+                fields.push(("statusCode".into(), ReportValue::Int(v.status_code as i64)));
+            }
+        }
+        fields
+    }
+}
+
+/// Report
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub report_type: ReportType,
+    /// URL of the document the report is about
+    pub url: String,
+    pub body: ReportBody,
+}
+
+/// ReportingObserver options
+#[derive(Debug, Clone, Default)]
+pub struct ReportingObserverOptions {
+    /// Report types to observe; all observable types when `None`
+    pub types: Option<Vec<ReportType>>,
+    /// Also deliver reports generated before `observe()`
+    pub buffered: bool,
+}
+
+/// Reporting observer
+#[derive(Debug)]
+pub struct ReportingObserver {
+    id: u64,
+    options: ReportingObserverOptions,
+    records: Vec<Report>,
+}
+
+impl ReportingObserver {
+    pub fn id(&self) -> u64 { self.id }
+
+    fn wants(&self, report_type: ReportType) -> bool {
+        report_type.is_observable() && self.options.types.as_ref().is_none_or(|t| t.contains(&report_type))
+    }
+
+    /// Take queued reports
+    pub fn take_records(&mut self) -> Vec<Report> {
+        std::mem::take(&mut self.records)
+    }
+}
+
+/// Reporting state of one document
+#[derive(Debug)]
+pub struct DocumentReporting {
+    url: String,
+    /// Endpoint name -> URL
+    endpoints: HashMap<String, String>,
+    observers: Vec<ReportingObserver>,
+    next_observer_id: u64,
+    buffer: VecDeque<Report>,
+    /// Deprecations and interventions are reported once per document
+    reported: HashSet<(ReportType, String)>,
+}
+
+impl DocumentReporting {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            endpoints: HashMap::new(),
+            observers: Vec::new(),
+            next_observer_id: 1,
+            buffer: VecDeque::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    pub fn url(&self) -> &str { &self.url }
+
+    /// Configure endpoints from response headers
+    pub fn apply_headers(&mut self, headers: &[(String, String)]) {
+        let origin = Url::parse(&self.url).map(|u| u.origin()).unwrap_or_default();
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case("reporting-endpoints") {
+                for endpoint in parse_reporting_endpoints(value, &origin) {
+                    self.endpoints.insert(endpoint.name, endpoint.url);
+                }
+            } else if name.eq_ignore_ascii_case("report-to") {
+                for group in parse_report_to(value) {
+                    // Reporting-Endpoints wins over the legacy header
+                    if let Some(endpoint) = group.endpoints.first() {
+                        self.endpoints.entry(group.group).or_insert_with(|| endpoint.url.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn endpoint(&self, name: &str) -> Option<&str> {
+        self.endpoints.get(name).map(String::as_str)
+    }
+
+    fn remove_endpoint_url(&mut self, url: &str) {
+        self.endpoints.retain(|_, u| u != url);
+    }
+
+    /// `new ReportingObserver(callback, options).observe()`
+    pub fn observe(&mut self, options: ReportingObserverOptions) -> u64 {
+        let id = self.next_observer_id;
+        self.next_observer_id += 1;
+        let mut observer = ReportingObserver { id, options, records: Vec::new() };
+        if observer.options.buffered {
+            observer.records = self.buffer.iter().filter(|r| observer.wants(r.report_type)).cloned().collect();
+        }
+        self.observers.push(observer);
+        id
+    }
+
+    pub fn disconnect(&mut self, id: u64) {
+        self.observers.retain(|o| o.id != id);
+    }
+
+    pub fn observer_mut(&mut self, id: u64) -> Option<&mut ReportingObserver> {
+        self.observers.iter_mut().find(|o| o.id == id)
+    }
+
+    /// Observer callbacks to run: each observer with queued reports
+    pub fn take_notifications(&mut self) -> Vec<(u64, Vec<Report>)> {
+        self.observers.iter_mut()
+            .filter(|o| !o.records.is_empty())
+            .map(|o| (o.id, o.take_records()))
+            .collect()
+    }
+
+    /// Queue a report for observers; returns the endpoint URL to upload to
+    fn queue(&mut self, report: Report, destination: &str) -> Option<String> {
+        if report.report_type.is_observable() {
+            for observer in self.observers.iter_mut().filter(|o| o.wants(report.report_type)) {
+                observer.records.push(report.clone());
+            }
+            if self.buffer.len() == REPORT_BUFFER_LIMIT {
+                self.buffer.pop_front();
+            }
+            self.buffer.push_back(report);
+        }
+        self.endpoint(destination).map(str::to_string)
+    }
+}
+
+/// Reporting for all documents
+#[derive(Debug)]
+pub struct ReportingManager {
+    documents: HashMap<u64, DocumentReporting>,
+    uploader: ReportUploader,
+    user_agent: String,
+}
+
+impl ReportingManager {
+    pub fn new(user_agent: &str) -> Self {
+        Self { documents: HashMap::new(), uploader: ReportUploader::new(), user_agent: user_agent.to_string() }
+    }
+
+    pub fn with_uploader(user_agent: &str, uploader: ReportUploader) -> Self {
+        Self { uploader, ..Self::new(user_agent) }
+    }
+
+    /// Start reporting for a new document
+    pub fn create_document(&mut self, document: u64, url: &str, headers: &[(String, String)]) -> &mut DocumentReporting {
+        let mut reporting = DocumentReporting::new(url);
+        reporting.apply_headers(headers);
+        self.documents.insert(document, reporting);
+        self.documents.get_mut(&document).expect("just inserted")
+    }
+
+    pub fn document(&self, document: u64) -> Option<&DocumentReporting> { self.documents.get(&document) }
+    pub fn document_mut(&mut self, document: u64) -> Option<&mut DocumentReporting> { self.documents.get_mut(&document) }
+
+    /// Document unloaded; reports already queued for upload are still sent
+    pub fn remove_document(&mut self, document: u64) {
+        self.documents.remove(&document);
+    }
+
+    /// Queue a report; false if the document is unknown
+    pub fn report(&mut self, document: u64, body: ReportBody, destination: &str) -> bool {
+        let Some(doc) = self.documents.get_mut(&document) else { return false };
+        let report = Report { report_type: body.report_type(), url: doc.url.clone(), body };
+        let fields = report.body.fields();
+        let (report_type, url) = (report.report_type, report.url.clone());
+        if let Some(endpoint) = doc.queue(report, destination) {
+            self.uploader.queue(QueuedReport::new(report_type.as_str(), &url, &self.user_agent, fields, &endpoint));
+        }
+        true
+    }
+
+    /// A deprecated feature was used; reported once per document and id
+    pub fn report_deprecation(
+        &mut self,
+        document: u64,
+        id: &str,
+        message: &str,
+        anticipated_removal: Option<&str>,
+        location: Option<SourceLocation>,
+    ) {
+        if !self.first_report(document, ReportType::Deprecation, id) { return; }
+        log::warn!("Deprecation [{}]: {}", id, message);
+        self.report(document, ReportBody::Deprecation {
+            id: id.to_string(),
+            message: message.to_string(),
+            anticipated_removal: anticipated_removal.map(str::to_string),
+            location,
+        }, DEFAULT_ENDPOINT);
+    }
+
+    /// The engine overrode page behavior (blocked, throttled, ...)
+    pub fn report_intervention(&mut self, document: u64, id: &str, message: &str, location: Option<SourceLocation>) {
+        if !self.first_report(document, ReportType::Intervention, id) { return; }
+        log::info!("Intervention [{}]: {}", id, message);
+        self.report(document, ReportBody::Intervention {
+            id: id.to_string(),
+            message: message.to_string(),
+            location,
+        }, DEFAULT_ENDPOINT);
+    }
+
+    /// CSP violation, sent to the policy's `report-to` group
+    pub fn report_csp_violation(&mut self, document: u64, violation: CspViolation, group: Option<&str>) {
+        self.report(document, ReportBody::CspViolation(violation), group.unwrap_or(DEFAULT_ENDPOINT));
+    }
+
+    /// The document's renderer crashed or hung; the document is gone afterwards
+    pub fn report_crash(&mut self, document: u64, reason: Option<&str>) {
+        self.report(document, ReportBody::Crash { reason: reason.map(str::to_string) }, DEFAULT_ENDPOINT);
+        self.remove_document(document);
+    }
+
+    fn first_report(&mut self, document: u64, report_type: ReportType, id: &str) -> bool {
+        self.documents.get_mut(&document).is_some_and(|d| d.reported.insert((report_type, id.to_string())))
+    }
+
+    /// Reports waiting for upload
+    pub fn pending_uploads(&self) -> usize { self.uploader.pending() }
+
+    /// Upload due batches and forget endpoints that are gone
+    pub fn deliver(&mut self, transport: &mut dyn ReportTransport) -> DeliveryOutcome {
+        let outcome = self.uploader.deliver(transport, Instant::now());
+        for url in &outcome.gone {
+            for doc in self.documents.values_mut() {
+                doc.remove_endpoint_url(url);
+            }
+        }
+        if outcome.dropped > 0 {
+            log::warn!("Dropped {} undeliverable reports", outcome.dropped);
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> Vec<(String, String)> {
+        vec![("Reporting-Endpoints".into(), r#"default="/reports", csp="https://csp.example/""#.into())]
+    }
+
+    #[test]
+    fn test_observers_and_buffering() {
+        let mut manager = ReportingManager::new("fOS");
+        let doc = manager.create_document(1, "https://site.example/page", &headers());
+        assert_eq!(doc.endpoint("default"), Some("https://site.example/reports"));
+        let early = doc.observe(ReportingObserverOptions { types: Some(vec![ReportType::Intervention]), buffered: false });
+
+        manager.report_deprecation(1, "sync-xhr", "Synchronous XMLHttpRequest is deprecated", None, None);
+        manager.report_deprecation(1, "sync-xhr", "Synchronous XMLHttpRequest is deprecated", None, None);
+        manager.report_intervention(1, "heavy-ad", "Ad frame unloaded", None);
+
+        let doc = manager.document_mut(1).unwrap();
+        let late = doc.observe(ReportingObserverOptions { types: None, buffered: true });
+        let notifications = doc.take_notifications();
+        assert_eq!(notifications.len(), 2);
+        let types = |id: u64| -> Vec<ReportType> {
+            notifications.iter().find(|(o, _)| *o == id).unwrap().1.iter().map(|r| r.report_type).collect()
+        };
+        assert_eq!(types(early), vec![ReportType::Intervention]);
+        assert_eq!(types(late), vec![ReportType::Deprecation, ReportType::Intervention]);
+        assert_eq!(manager.pending_uploads(), 2);
+    }
+
+    struct Sink(Vec<String>);
+
+    impl ReportTransport for Sink {
+        fn post(&mut self, url: &str, _content_type: &str, _body: Vec<u8>) -> Result<u16, fos_net::NetError> {
+            self.0.push(url.to_string());
+            Ok(if url.contains("csp") { 410 } else { 204 })
+        }
+    }
+
+    #[test]
+    fn test_crash_and_csp_delivery() {
+        let mut manager = ReportingManager::with_uploader("fOS", ReportUploader::with_batch_delay(std::time::Duration::ZERO));
+        manager.create_document(1, "https://site.example/", &headers());
+        let observer = manager.document_mut(1).unwrap().observe(ReportingObserverOptions::default());
+        manager.report_csp_violation(1, CspViolation {
+            document_uri: "https://site.example/".into(),
+            violated_directive: "script-src".into(),
+            effective_directive: "script-src-elem".into(),
+            original_policy: "script-src 'self'; report-to csp".into(),
+            blocked_uri: "https://evil.example/x.js".into(),
+            status_code: 200,
+        }, Some("csp"));
+        assert_eq!(manager.document_mut(1).unwrap().observer_mut(observer).unwrap().take_records().len(), 1);
+
+        manager.report_crash(1, Some("oom"));
+        assert!(manager.document(1).is_none());
+
+        let mut sink = Sink(Vec::new());
+        let outcome = manager.deliver(&mut sink);
+        assert_eq!(outcome.delivered, 1);
+        assert_eq!(outcome.gone, vec!["https://csp.example/".to_string()]);
+        assert_eq!(sink.0.len(), 2);
+    }
+}
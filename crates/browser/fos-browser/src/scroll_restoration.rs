@@ -0,0 +1,284 @@
+//! Scroll Restoration and Scroll Anchoring
+//!
+//! Scroll positions are saved per history entry, for the root scroller and
+//! every scroll container, and restored on back/forward once layout has
+//! grown tall enough to hold them. `history.scrollRestoration = 'manual'`
+//! opts an entry out. Scroll anchoring (`overflow-anchor`) keeps visible
+//! content still while late-loading images change the layout above it.
+
+use std::collections::HashMap;
+
+use fos_css::computed::OverflowAnchor;
+
+/// `history.scrollRestoration`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScrollRestoration {
+    #[default]
+    Auto,
+    Manual,
+}
+
+impl ScrollRestoration {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "auto" => Some(Self::Auto),
+            "manual" => Some(Self::Manual),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Manual => "manual",
+        }
+    }
+}
+
+/// Scroll offset of one scroller
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScrollOffset {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl ScrollOffset {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    fn min(self, other: Self) -> Self {
+        Self::new(self.x.min(other.x), self.y.min(other.y))
+    }
+
+    fn fits_within(self, max: Self) -> bool {
+        self.x <= max.x && self.y <= max.y
+    }
+}
+
+/// Offsets of the root scroller and scroll containers in a document
+///
+/// Containers are keyed by a path that survives reloads, such as `#id` or
+/// the child-index path from the root.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScrollSnapshot {
+    pub root: ScrollOffset,
+    pub containers: Vec<(String, ScrollOffset)>,
+}
+
+impl ScrollSnapshot {
+    pub fn new(root: ScrollOffset) -> Self {
+        Self { root, containers: Vec::new() }
+    }
+
+    /// Add a scroll container
+    pub fn with_container(mut self, key: &str, offset: ScrollOffset) -> Self {
+        self.containers.push((key.to_string(), offset));
+        self
+    }
+
+    /// Offset of a scroll container
+    pub fn container(&self, key: &str) -> Option<ScrollOffset> {
+        self.containers.iter().find(|(k, _)| k == key).map(|(_, offset)| *offset)
+    }
+}
+
+/// Saved scroll state of one history entry
+#[derive(Debug, Clone, Default)]
+struct EntryScroll {
+    mode: ScrollRestoration,
+    snapshot: Option<ScrollSnapshot>,
+}
+
+/// Per-entry scroll state keyed by navigation entry key
+#[derive(Debug, Default)]
+pub struct ScrollRestorer {
+    entries: HashMap<String, EntryScroll>,
+    /// Target of a restore waiting for layout
+    pending: Option<ScrollSnapshot>,
+}
+
+impl ScrollRestorer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restoration mode of an entry
+    pub fn mode(&self, key: &str) -> ScrollRestoration {
+        self.entries.get(key).map(|entry| entry.mode).unwrap_or_default()
+    }
+
+    /// Set the restoration mode of an entry
+    pub fn set_mode(&mut self, key: &str, mode: ScrollRestoration) {
+        self.entries.entry(key.to_string()).or_default().mode = mode;
+    }
+
+    /// Give an entry created by pushState the mode of the entry it came from
+    pub fn inherit(&mut self, from: &str, to: &str) {
+        let mode = self.mode(from);
+        self.set_mode(to, mode);
+    }
+
+    /// Save scroll positions before leaving an entry
+    pub fn save(&mut self, key: &str, snapshot: ScrollSnapshot) {
+        self.entries.entry(key.to_string()).or_default().snapshot = Some(snapshot);
+    }
+
+    /// Saved scroll positions of an entry
+    pub fn snapshot(&self, key: &str) -> Option<&ScrollSnapshot> {
+        self.entries.get(key)?.snapshot.as_ref()
+    }
+
+    /// Start restoring an entry after traversing to it; false if the entry
+    /// is in manual mode or was never scrolled
+    pub fn begin_restore(&mut self, key: &str) -> bool {
+        self.pending = match self.entries.get(key) {
+            Some(EntryScroll { mode: ScrollRestoration::Auto, snapshot: Some(snapshot) }) => {
+                Some(snapshot.clone())
+            }
+            _ => None,
+        };
+        self.pending.is_some()
+    }
+
+    /// Whether a restore is waiting for layout
+    pub fn is_restoring(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Apply the pending restore after a layout pass
+    ///
+    /// `extents` holds the maximum scroll offsets layout produced. Offsets
+    /// are clamped to them; the restore stays pending while content is too
+    /// short to reach the target (images still loading, containers not yet
+    /// laid out) until the document has finished loading.
+    pub fn on_layout(&mut self, extents: &ScrollSnapshot, loaded: bool) -> Option<ScrollSnapshot> {
+        let target = self.pending.as_ref()?;
+        let mut reached = target.root.fits_within(extents.root);
+        let mut apply = ScrollSnapshot::new(target.root.min(extents.root));
+        for (key, offset) in &target.containers {
+            match extents.container(key) {
+                Some(max) => {
+                    reached &= offset.fits_within(max);
+                    apply.containers.push((key.clone(), offset.min(max)));
+                }
+                None => reached = false,
+            }
+        }
+        if reached || loaded {
+            self.pending = None;
+        }
+        Some(apply)
+    }
+
+    /// The user scrolled; a pending restore must not override them
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// Forget entries that are no longer in the session history
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.entries.retain(|key, _| keep(key));
+    }
+}
+
+/// A box considered for scroll anchoring, in document order
+#[derive(Debug, Clone)]
+pub struct AnchorCandidate {
+    pub key: String,
+    /// Offset from the top of the scroller's content
+    pub top: f32,
+    pub height: f32,
+    pub overflow_anchor: OverflowAnchor,
+}
+
+/// Node chosen to keep still across a layout change
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrollAnchor {
+    pub key: String,
+    pub top: f32,
+}
+
+impl ScrollAnchor {
+    /// Pick the first visible candidate that allows anchoring; none when
+    /// the scroller opts out or is not scrolled
+    pub fn select(
+        scroller: OverflowAnchor,
+        scroll_top: f32,
+        viewport_height: f32,
+        candidates: &[AnchorCandidate],
+    ) -> Option<Self> {
+        if scroller == OverflowAnchor::None || scroll_top <= 0.0 {
+            return None;
+        }
+        let bottom = scroll_top + viewport_height;
+        candidates
+            .iter()
+            .filter(|c| c.overflow_anchor == OverflowAnchor::Auto)
+            .find(|c| c.top + c.height > scroll_top && c.top < bottom)
+            .map(|c| Self { key: c.key.clone(), top: c.top })
+    }
+
+    /// Scroll adjustment that keeps the anchor in place after layout
+    pub fn adjustment(&self, candidates: &[AnchorCandidate]) -> f32 {
+        candidates
+            .iter()
+            .find(|c| c.key == self.key)
+            .map(|c| c.top - self.top)
+            .unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_waits_for_layout() {
+        let mut restorer = ScrollRestorer::new();
+        let saved = ScrollSnapshot::new(ScrollOffset::new(0.0, 1200.0))
+            .with_container("#list", ScrollOffset::new(0.0, 300.0));
+        restorer.save("a", saved.clone());
+        assert!(restorer.begin_restore("a"));
+
+        // Images above the target have not loaded yet
+        let short = ScrollSnapshot::new(ScrollOffset::new(0.0, 800.0));
+        let applied = restorer.on_layout(&short, false).unwrap();
+        assert_eq!(applied.root.y, 800.0);
+        assert!(restorer.is_restoring());
+
+        let tall = ScrollSnapshot::new(ScrollOffset::new(0.0, 2000.0))
+            .with_container("#list", ScrollOffset::new(0.0, 500.0));
+        assert_eq!(restorer.on_layout(&tall, false), Some(saved));
+        assert!(!restorer.is_restoring());
+
+        // Manual mode is inherited by pushed entries
+        restorer.set_mode("a", ScrollRestoration::Manual);
+        restorer.inherit("a", "b");
+        restorer.save("b", ScrollSnapshot::default());
+        assert!(!restorer.begin_restore("b"));
+        assert_eq!(ScrollRestoration::from_str("manual"), Some(restorer.mode("b")));
+    }
+
+    #[test]
+    fn test_anchor_adjustment() {
+        let candidate = |key: &str, top: f32, overflow_anchor| AnchorCandidate {
+            key: key.to_string(),
+            top,
+            height: 100.0,
+            overflow_anchor,
+        };
+        let before = [
+            candidate("img", 0.0, OverflowAnchor::Auto),
+            candidate("ad", 450.0, OverflowAnchor::None),
+            candidate("p", 600.0, OverflowAnchor::Auto),
+        ];
+        let anchor = ScrollAnchor::select(OverflowAnchor::Auto, 500.0, 400.0, &before).unwrap();
+        assert_eq!(anchor.key, "p");
+
+        // The image above loads and pushes the paragraph down
+        let after = [candidate("img", 0.0, OverflowAnchor::Auto), candidate("p", 850.0, OverflowAnchor::Auto)];
+        assert_eq!(anchor.adjustment(&after), 250.0);
+        assert!(ScrollAnchor::select(OverflowAnchor::None, 500.0, 400.0, &before).is_none());
+    }
+}
@@ -1,12 +1,31 @@
 //! Security Integration
 //!
 //! Integrates fos-security features: CSP, Sandbox, Privacy, Tracking Protection.
+//! Workers get their own policy containers, so their script fetches are
+//! checked against the page and their sub-fetches against their own CSP,
+//! referrer policy and COEP.
+//! Storage access grants are browser-wide and shared with the pages' cookie
+//! state, so an embedded document that is granted access sees its
+//! unpartitioned cookies and storage.
+//! Privacy signal preferences are shared with the network, so requests
+//! follow them as soon as they change.
+//! Bounce tracking mitigation follows each tab's redirect chain and purges
+//! the storage of sites that bounce users through themselves to set it.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 use fos_security::{
     ContentSecurityPolicy, CspViolation,
     SandboxFlags,
-    ReferrerPolicy, CookiePolicy, TrackingProtection,
+    ReferrerPolicy, CookiePolicy, TrackingProtection, ContentBlocking, FingerprintingProtection,
+    PolicyContainer, WorkerKind, WorkerPolicyError,
+    CookieAccess, StorageAccess, StorageKey,
+    BounceTracking, BounceTrackingStats, PrivacySignals, DoNotTrack,
 };
+use fos_security::coop_coep::{CoepPolicy, CorpPolicy};
+use fos_js::DeviceInfo;
+use fos_js::engine::TimeZone;
 
 /// Security manager for the browser
 pub struct SecurityManager {
@@ -18,10 +37,24 @@ pub struct SecurityManager {
     pub referrer_policy: ReferrerPolicy,
     /// Cookie policy
     pub cookie_policy: CookiePolicy,
+    /// Storage Access API grants
+    pub storage_access: Arc<Mutex<StorageAccess>>,
+    /// Bounce tracking mitigation, on with tracking protection
+    pub bounce_tracking: BounceTracking,
     /// Tracking protection
     pub tracking: TrackingProtection,
-    /// Do Not Track enabled
-    pub dnt_enabled: bool,
+    /// Third-party fonts, scripts and frames blocked by category, with
+    /// per-site settings
+    pub content_blocking: ContentBlocking,
+    /// Fingerprinting resistance, browser-wide with per-site exceptions
+    pub fingerprinting: FingerprintingProtection,
+    /// Global Privacy Control and Do Not Track, browser-wide with per-site
+    /// exemptions, shared with the network
+    pub privacy_signals: Arc<Mutex<PrivacySignals>>,
+    /// Cross-Origin-Embedder-Policy for current page
+    pub coep: CoepPolicy,
+    /// Policy containers of workers by kind and ID
+    workers: HashMap<(WorkerKind, u64), PolicyContainer>,
     /// CSP violations log
     violations: Vec<CspViolation>,
 }
@@ -37,8 +70,14 @@ impl SecurityManager {
             sandbox: SandboxFlags::new(),
             referrer_policy: ReferrerPolicy::default(),
             cookie_policy: CookiePolicy::default(),
+            storage_access: Arc::new(Mutex::new(StorageAccess::new())),
+            bounce_tracking: BounceTracking::new(),
             tracking,
-            dnt_enabled: false,
+            content_blocking: ContentBlocking::new(),
+            fingerprinting: FingerprintingProtection::new(),
+            privacy_signals: Arc::new(Mutex::new(PrivacySignals::new())),
+            coep: CoepPolicy::default(),
+            workers: HashMap::new(),
             violations: Vec::new(),
         }
     }
@@ -63,6 +102,11 @@ impl SecurityManager {
         }
     }
     
+    /// Parse Cross-Origin-Embedder-Policy from response header
+    pub fn parse_coep(&mut self, header: &str) {
+        self.coep = CoepPolicy::parse(header);
+    }
+    
     /// Check if script is allowed
     pub fn allows_script(&self, src: &str, origin: &str) -> bool {
         // Check sandbox first
@@ -141,11 +185,119 @@ impl SecurityManager {
         true
     }
     
+    // === Workers ===
+    
+    /// Policy container of the current page
+    pub fn policy_container(&self) -> PolicyContainer {
+        PolicyContainer {
+            csp: self.csp.clone(),
+            referrer_policy: self.referrer_policy,
+            coep: self.coep,
+        }
+    }
+    
+    /// Check a worker script before fetching it; `creator` is the worker
+    /// starting a nested worker, if any
+    pub fn check_worker_script(
+        &self,
+        creator: Option<(WorkerKind, u64)>,
+        kind: WorkerKind,
+        url: &str,
+        origin: &str,
+    ) -> Result<(), WorkerPolicyError> {
+        if !self.sandbox.flags.is_empty() && !self.sandbox.allows_scripts() {
+            return Err(WorkerPolicyError::Sandboxed(url.to_string()));
+        }
+        match creator.and_then(|key| self.workers.get(&key)) {
+            Some(container) => container.check_worker_script(kind, url, origin),
+            None => self.policy_container().check_worker_script(kind, url, origin),
+        }
+    }
+    
+    /// Referrer for a worker script fetch, from its creator's policy
+    pub fn worker_script_referrer(&self, creator: Option<(WorkerKind, u64)>, source_url: &str, dest_url: &str) -> Option<String> {
+        match creator.and_then(|key| self.workers.get(&key)) {
+            Some(container) => container.referrer(source_url, dest_url),
+            None => self.get_referrer(source_url, dest_url),
+        }
+    }
+    
+    /// Set up a worker's policy container from its script response
+    pub fn attach_worker(
+        &mut self,
+        creator: Option<(WorkerKind, u64)>,
+        kind: WorkerKind,
+        id: u64,
+        url: &str,
+        headers: &HashMap<String, String>,
+    ) -> Result<(), WorkerPolicyError> {
+        let parent = match creator.and_then(|key| self.workers.get(&key)) {
+            Some(container) => container.clone(),
+            None => self.policy_container(),
+        };
+        match parent.for_worker(kind, url, headers) {
+            Ok(container) => {
+                self.workers.insert((kind, id), container);
+                Ok(())
+            }
+            Err(e) => {
+                log::warn!("{}", e);
+                Err(e)
+            }
+        }
+    }
+    
+    /// Policy container of a worker
+    pub fn worker_policy(&self, kind: WorkerKind, id: u64) -> Option<&PolicyContainer> {
+        self.workers.get(&(kind, id))
+    }
+    
+    /// Check a fetch made by a worker against its CSP; unknown workers
+    /// may not fetch
+    pub fn worker_allows(&self, kind: WorkerKind, id: u64, directive: &str, url: &str, origin: &str) -> bool {
+        self.worker_policy(kind, id)
+            .is_some_and(|container| container.allows(directive, url, origin))
+    }
+    
+    /// Referrer for a fetch made by a worker
+    pub fn worker_referrer(&self, kind: WorkerKind, id: u64, source_url: &str, dest_url: &str) -> Option<String> {
+        self.worker_policy(kind, id)?.referrer(source_url, dest_url)
+    }
+    
+    /// Check a worker's response against its embedder policy
+    pub fn worker_can_embed(&self, kind: WorkerKind, id: u64, corp: CorpPolicy, resource_origin: &str, worker_origin: &str) -> bool {
+        self.worker_policy(kind, id)
+            .is_some_and(|container| container.isolation_enforcer().can_embed(corp, resource_origin, worker_origin))
+    }
+    
+    /// Drop a terminated worker's policy container
+    pub fn detach_worker(&mut self, kind: WorkerKind, id: u64) {
+        self.workers.remove(&(kind, id));
+    }
+    
     /// Check if URL should be blocked by tracking protection
     pub fn is_tracker(&self, url: &str) -> bool {
         self.tracking.should_block(url)
     }
     
+    /// Hardware values, time zone and Global Privacy Control a page at `url`
+    /// sees: clamped, or coarsened when fingerprinting resistance applies to
+    /// the site
+    pub fn device_info(&self, url: &str) -> DeviceInfo {
+        let processors = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let time_zone = if self.fingerprinting.applies_to(url) {
+            TimeZone::utc()
+        } else {
+            TimeZone::host().clone()
+        };
+        DeviceInfo {
+            hardware_concurrency: self.fingerprinting.hardware_concurrency(url, processors),
+            device_memory: self.fingerprinting.device_memory(url, system_memory().unwrap_or(8 << 30)),
+            time_zone,
+            global_privacy_control: self.privacy_signals.lock().unwrap().global_privacy_control(url),
+        }
+    }
+    
     /// Get referrer for request
     pub fn get_referrer(&self, source_url: &str, dest_url: &str) -> Option<String> {
         self.referrer_policy.compute_referrer(source_url, dest_url)
@@ -175,6 +327,11 @@ impl SecurityManager {
         true
     }
     
+    /// Reporting endpoint group named by the CSP `report-to` directive
+    pub fn csp_report_to(&self) -> Option<&str> {
+        self.csp.as_ref()?.get(fos_security::csp::REPORT_TO)?.first().map(String::as_str)
+    }
+    
     /// Log a CSP violation
     pub fn report_violation(&mut self, violation: CspViolation) {
         log::warn!("CSP violation: {} blocked {}", 
@@ -192,22 +349,79 @@ impl SecurityManager {
         self.violations.clear();
     }
     
+    /// Cookie checks for a document at `url` embedded in `top_level_url`,
+    /// under the cookie policy and any storage access grant
+    pub fn cookie_access(&self, top_level_url: &str, url: &str) -> CookieAccess {
+        let key = StorageKey::new(top_level_url, url);
+        CookieAccess::new(url)
+            .with_policy(self.cookie_policy)
+            .third_party(key.is_third_party())
+            .with_storage_access(self.storage_access.lock().unwrap().has_storage_access(&key))
+    }
+    
+    /// Storage key a document at `url` embedded in `top_level_url` keeps its
+    /// state under: unpartitioned while it has storage access
+    pub fn storage_key(&self, top_level_url: &str, url: &str) -> StorageKey {
+        self.storage_access.lock().unwrap().effective_key(&StorageKey::new(top_level_url, url))
+    }
+    
+    /// The user interacted with a top-level page in a tab, which storage
+    /// access heuristics count as a first-party visit and which clears the
+    /// site of bounce tracking
+    pub fn record_interaction(&mut self, tab: u64, url: &str) {
+        self.storage_access.lock().unwrap().record_interaction(url);
+        self.bounce_tracking.interaction(tab, url);
+    }
+    
+    /// A tab committed a navigation whose response had `headers`; returns
+    /// sites the chain it ended revealed as bounce trackers
+    pub fn record_navigation(&mut self, tab: u64, url: &str, user_initiated: bool, headers: &[(String, String)]) -> Vec<String> {
+        if !self.tracking.enabled {
+            return Vec::new();
+        }
+        let detected = self.bounce_tracking.navigate(tab, url, user_initiated);
+        if headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("set-cookie")) {
+            self.bounce_tracking.storage_written(tab, url);
+        }
+        detected
+    }
+    
+    /// Purge bounce trackers whose grace period ran out: their storage
+    /// access grants go here, and the caller clears their cookies and
+    /// storage
+    pub fn purge_bounce_trackers(&mut self) -> Vec<String> {
+        let sites = self.bounce_tracking.purge();
+        let mut grants = self.storage_access.lock().unwrap();
+        for site in &sites {
+            grants.clear_site(site);
+            log::info!("Purged storage of bounce tracker {}", site);
+        }
+        sites
+    }
+    
     /// Reset for new page load
     pub fn reset(&mut self) {
         self.csp = None;
         self.sandbox = SandboxFlags::new();
-        // Keep referrer_policy, cookie_policy, and tracking as browser-wide settings
+        self.coep = CoepPolicy::default();
+        // Service workers outlive the page
+        self.workers.retain(|(kind, _), _| *kind == WorkerKind::Service);
+        // Keep referrer_policy, cookie_policy, tracking, content blocking and
+        // privacy signals as browser-wide settings
         self.violations.clear();
     }
     
     /// Get statistics
     pub fn stats(&self) -> SecurityStats {
+        let signals = self.privacy_signals.lock().unwrap();
         SecurityStats {
             has_csp: self.csp.is_some(),
             is_sandboxed: !self.sandbox.flags.is_empty(),
             tracking_enabled: self.tracking.enabled,
-            dnt_enabled: self.dnt_enabled,
+            dnt_enabled: signals.do_not_track == DoNotTrack::Enabled,
+            gpc_enabled: signals.global_privacy_control,
             violation_count: self.violations.len(),
+            bounce_tracking: self.bounce_tracking.stats(),
         }
     }
 }
@@ -218,6 +432,14 @@ impl Default for SecurityManager {
     }
 }
 
+/// Installed RAM in bytes, from `/proc/meminfo`
+fn system_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
 /// Security statistics
 #[derive(Debug, Clone)]
 pub struct SecurityStats {
@@ -225,7 +447,10 @@ pub struct SecurityStats {
     pub is_sandboxed: bool,
     pub tracking_enabled: bool,
     pub dnt_enabled: bool,
+    pub gpc_enabled: bool,
     pub violation_count: usize,
+    /// Bounce tracking counters for the tracking protection dashboard
+    pub bounce_tracking: BounceTrackingStats,
 }
 
 #[cfg(test)]
@@ -256,6 +481,38 @@ mod tests {
         assert!(!manager.sandbox.allows_popups());
     }
     
+    #[test]
+    fn test_worker_policies() {
+        let mut manager = SecurityManager::new();
+        manager.parse_csp("default-src 'self'; worker-src 'self'; connect-src https://api.example.com");
+        manager.parse_coep("require-corp");
+        let origin = "https://example.com";
+        
+        assert!(manager.check_worker_script(None, WorkerKind::Dedicated, "https://evil.com/w.js", origin).is_err());
+        assert!(manager.check_worker_script(None, WorkerKind::Dedicated, "https://example.com/w.js", origin).is_ok());
+        
+        // A worker without COEP cannot be created by a require-corp page
+        assert!(manager.attach_worker(None, WorkerKind::Dedicated, 1, "https://example.com/w.js", &HashMap::new()).is_err());
+        let headers: HashMap<String, String> = [
+            ("cross-origin-embedder-policy".to_string(), "require-corp".to_string()),
+            ("content-security-policy".to_string(), "connect-src 'self'".to_string()),
+        ].into_iter().collect();
+        manager.attach_worker(None, WorkerKind::Dedicated, 1, "https://example.com/w.js", &headers).unwrap();
+        
+        // Sub-fetches follow the worker's own CSP, not the page's
+        assert!(manager.worker_allows(WorkerKind::Dedicated, 1, "connect-src", "https://example.com/data", origin));
+        assert!(!manager.worker_allows(WorkerKind::Dedicated, 1, "connect-src", "https://api.example.com/", origin));
+        assert!(!manager.worker_can_embed(WorkerKind::Dedicated, 1, CorpPolicy::None, "https://cdn.other.com", origin));
+        
+        // Blob workers inherit from the worker that creates them
+        let creator = Some((WorkerKind::Dedicated, 1));
+        manager.attach_worker(creator, WorkerKind::Dedicated, 2, "blob:https://example.com/x", &HashMap::new()).unwrap();
+        assert!(!manager.worker_allows(WorkerKind::Dedicated, 2, "connect-src", "https://api.example.com/", origin));
+        
+        manager.detach_worker(WorkerKind::Dedicated, 1);
+        assert!(manager.worker_policy(WorkerKind::Dedicated, 1).is_none());
+    }
+    
     #[test]
     fn test_tracking_protection() {
         let manager = SecurityManager::new();
@@ -263,6 +520,48 @@ mod tests {
         assert!(!manager.is_tracker("https://example.com/script.js"));
     }
     
+    #[test]
+    fn test_bounce_tracking() {
+        let mut manager = SecurityManager::new();
+        let cookie = vec![("Set-Cookie".to_string(), "uid=1".to_string())];
+        manager.record_navigation(1, "https://news.com/", true, &[]);
+        manager.record_navigation(1, "https://bounce.net/r", false, &cookie);
+        manager.record_navigation(1, "https://shop.com/", false, &[]);
+        assert_eq!(manager.record_navigation(1, "https://search.org/", true, &[]), vec!["https://bounce.net"]);
+        assert_eq!(manager.stats().bounce_tracking.pending_purge, 1);
+        
+        // Interacting with the site before the purge spares it
+        manager.record_interaction(2, "https://bounce.net/login");
+        assert_eq!(manager.stats().bounce_tracking.pending_purge, 0);
+        assert!(manager.purge_bounce_trackers().is_empty());
+    }
+    
+    #[test]
+    fn test_device_info() {
+        let mut manager = SecurityManager::new();
+        let device = manager.device_info("https://example.com/");
+        assert!((1..=16).contains(&device.hardware_concurrency));
+        assert!(device.device_memory >= 0.25 && device.device_memory <= 8.0);
+        
+        manager.fingerprinting.enabled = true;
+        let device = manager.device_info("https://example.com/");
+        assert_eq!(device.hardware_concurrency, 4);
+        assert_eq!(device.device_memory, 8.0);
+        assert_eq!(device.time_zone.name(), "UTC");
+        
+        manager.fingerprinting.set_site("example.com", false);
+        assert_eq!(manager.device_info("https://example.com/").time_zone, *TimeZone::host());
+        
+        assert!(!manager.device_info("https://example.com/").global_privacy_control);
+        {
+            let mut signals = manager.privacy_signals.lock().unwrap();
+            signals.global_privacy_control = true;
+            signals.set_site("example.com", false);
+        }
+        assert!(manager.device_info("https://other.org/").global_privacy_control);
+        assert!(!manager.device_info("https://example.com/").global_privacy_control);
+    }
+    
     #[test]
     fn test_referrer_policy() {
         let mut manager = SecurityManager::new();
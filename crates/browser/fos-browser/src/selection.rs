@@ -1,8 +1,10 @@
 //! Text Selection API
 //!
-//! DOM text selection and ranges.
+//! DOM text selection and ranges. Word and sentence movement use the
+//! Unicode segmentation rules of fos-text.
 
 use fos_dom::NodeId;
+use fos_text::segmentation::{self, Granularity};
 
 /// A point in text content
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -20,6 +22,42 @@ pub enum SelectionType {
     Range,
 }
 
+/// Unit a selection is moved or extended by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectionGranularity {
+    #[default]
+    Character,
+    Word,
+    Sentence,
+}
+
+impl SelectionGranularity {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "character" => Some(Self::Character),
+            "word" => Some(Self::Word),
+            "sentence" => Some(Self::Sentence),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Character => "character",
+            Self::Word => "word",
+            Self::Sentence => "sentence",
+        }
+    }
+
+    fn segmentation(self) -> Granularity {
+        match self {
+            Self::Character => Granularity::Grapheme,
+            Self::Word => Granularity::Word,
+            Self::Sentence => Granularity::Sentence,
+        }
+    }
+}
+
 /// Text selection state
 #[derive(Debug, Clone)]
 pub struct Selection {
@@ -65,6 +103,11 @@ pub struct SelectionManager {
     selection: Selection,
     /// Selected text content (cached)
     selected_text: String,
+    /// Granularity of the gesture that made the selection; a double-click
+    /// drag extends by words
+    granularity: SelectionGranularity,
+    /// The word a double-click selected, kept while dragging
+    initial_word: Option<(usize, usize)>,
 }
 
 impl SelectionManager {
@@ -87,6 +130,8 @@ impl SelectionManager {
             selection_type: SelectionType::Caret,
         };
         self.selected_text.clear();
+        self.granularity = SelectionGranularity::Character;
+        self.initial_word = None;
     }
     
     /// Set selection range
@@ -98,6 +143,86 @@ impl SelectionManager {
             selection_type: if anchor == focus { SelectionType::Caret } else { SelectionType::Range },
         };
         self.selected_text = text.to_string();
+        self.granularity = SelectionGranularity::Character;
+        self.initial_word = None;
+    }
+    
+    /// Double-click: select the word at `offset` in a text node's content
+    pub fn select_word_at(&mut self, node: NodeId, content: &str, offset: usize) {
+        let (start, end) = segmentation::word_at(content, offset);
+        self.select_offsets(node, content, start, end);
+        self.granularity = SelectionGranularity::Word;
+        self.initial_word = Some((start, end));
+    }
+    
+    /// Drag to `offset` in the anchor's text node. After a double-click the
+    /// selection grows by whole words and keeps the first word selected.
+    pub fn drag_to(&mut self, content: &str, offset: usize) {
+        let Some(anchor) = self.selection.anchor else {
+            return;
+        };
+        let offset = offset.min(content.len());
+        match (self.granularity, self.initial_word) {
+            (SelectionGranularity::Word, Some((word_start, word_end))) => {
+                let (start, end) = segmentation::word_at(content, offset);
+                let (start, end) = (start.min(word_start), end.max(word_end));
+                // Keep the anchor on the side away from the pointer
+                if offset < word_start {
+                    self.select_offsets(anchor.node, content, end, start);
+                } else {
+                    self.select_offsets(anchor.node, content, start, end);
+                }
+                self.granularity = SelectionGranularity::Word;
+                self.initial_word = Some((word_start, word_end));
+            }
+            _ => {
+                let focus = TextPoint { node: anchor.node, offset };
+                let (start, end) = (anchor.offset.min(offset), anchor.offset.max(offset));
+                self.extend_to(focus, content.get(start..end).unwrap_or(""));
+            }
+        }
+    }
+    
+    /// `selection.modify(alter, direction, granularity)` within the focus
+    /// node's content: move the caret, or extend the focus, by a grapheme
+    /// cluster, word or sentence
+    pub fn modify(&mut self, extend: bool, forward: bool, granularity: SelectionGranularity, content: &str) {
+        let Some(focus) = self.selection.focus else {
+            return;
+        };
+        let offset = focus.offset.min(content.len());
+        let target = match (granularity, forward) {
+            // Words move to the end of the next word or the start of the
+            // previous one, skipping spaces and punctuation
+            (SelectionGranularity::Word, true) => segmentation::segments(content, Granularity::Word)
+                .into_iter()
+                .find(|s| s.is_word_like == Some(true) && s.index + s.segment.len() > offset)
+                .map_or(content.len(), |s| s.index + s.segment.len()),
+            (SelectionGranularity::Word, false) => segmentation::segments(content, Granularity::Word)
+                .into_iter()
+                .rev()
+                .find(|s| s.is_word_like == Some(true) && s.index < offset)
+                .map_or(0, |s| s.index),
+            (granularity, true) => segmentation::next_boundary(content, offset, granularity.segmentation()),
+            (granularity, false) => segmentation::previous_boundary(content, offset, granularity.segmentation()),
+        };
+        
+        match self.selection.anchor.filter(|_| extend) {
+            Some(anchor) => {
+                let (start, end) = (anchor.offset.min(target), anchor.offset.max(target));
+                self.extend_to(TextPoint { node: focus.node, offset: target }, content.get(start..end).unwrap_or(""));
+            }
+            None => self.set_caret(focus.node, target),
+        }
+    }
+    
+    fn select_offsets(&mut self, node: NodeId, content: &str, anchor: usize, focus: usize) {
+        let (start, end) = (anchor.min(focus), anchor.max(focus));
+        self.set_range(
+            TextPoint { node, offset: anchor },
+            TextPoint { node, offset: focus },
+            content.get(start..end).unwrap_or(""),
+        );
     }
     
     /// Extend selection to point
@@ -200,4 +325,27 @@ mod tests {
         assert!(!mgr.get_selection().is_collapsed);
         assert_eq!(mgr.get_text(), "Hello World");
     }
+    
+    #[test]
+    fn test_word_granularity() {
+        let mut mgr = SelectionManager::new();
+        let node = NodeId(1);
+        let content = "Don't stop, café au lait.";
+        
+        // Double-click selects the word, dragging extends by words
+        mgr.select_word_at(node, content, 2);
+        assert_eq!(mgr.get_text(), "Don't");
+        mgr.drag_to(content, 14);
+        assert_eq!(mgr.get_text(), "Don't stop, café");
+        
+        // Caret movement by words and grapheme clusters
+        mgr.set_caret(node, 0);
+        mgr.modify(false, true, SelectionGranularity::Word, content);
+        assert_eq!(mgr.get_selection().focus.unwrap().offset, 5);
+        mgr.modify(true, true, SelectionGranularity::Word, content);
+        assert_eq!(mgr.get_text(), " stop");
+        mgr.set_caret(node, 17);
+        mgr.modify(false, false, SelectionGranularity::Character, content);
+        assert_eq!(mgr.get_selection().focus.unwrap().offset, 15);
+    }
 }
@@ -95,6 +95,10 @@ impl Gyroscope {
         self.active = false;
     }
     
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+    
     pub fn reading(&self) -> Option<Vec3> {
         self.reading.map(|r| r.value)
     }
@@ -136,9 +140,20 @@ impl Magnetometer {
         self.active = false;
     }
     
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+    
     pub fn reading(&self) -> Option<Vec3> {
         self.reading.map(|r| r.value)
     }
+    
+    pub fn update(&mut self, x: f64, y: f64, z: f64, timestamp: f64) {
+        self.reading = Some(SensorReading {
+            value: Vec3 { x, y, z },
+            timestamp,
+        });
+    }
 }
 
 /// Ambient light sensor
@@ -170,6 +185,10 @@ impl AmbientLightSensor {
         self.active = false;
     }
     
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+    
     pub fn illuminance(&self) -> Option<f64> {
         self.illuminance
     }
@@ -197,6 +216,18 @@ pub struct DeviceMotion {
     pub interval: f64,
 }
 
+/// Platform sensor hardware, implemented by embedders (and by DevTools
+/// emulation); `None` means the sensor has no reading or doesn't exist
+pub trait SensorProvider: Send + std::fmt::Debug {
+    fn accelerometer(&mut self) -> Option<Vec3> { None }
+    fn gyroscope(&mut self) -> Option<Vec3> { None }
+    fn magnetometer(&mut self) -> Option<Vec3> { None }
+    /// Ambient light in lux
+    fn illuminance(&mut self) -> Option<f64> { None }
+    fn orientation(&mut self) -> Option<DeviceOrientation> { None }
+    fn motion(&mut self) -> Option<DeviceMotion> { None }
+}
+
 /// Sensors manager
 #[derive(Debug, Default)]
 pub struct SensorsManager {
@@ -206,6 +237,7 @@ pub struct SensorsManager {
     pub ambient_light: AmbientLightSensor,
     device_orientation: Option<DeviceOrientation>,
     device_motion: Option<DeviceMotion>,
+    provider: Option<Box<dyn SensorProvider>>,
 }
 
 impl SensorsManager {
@@ -213,6 +245,47 @@ impl SensorsManager {
         Self::default()
     }
     
+    pub fn set_provider(&mut self, provider: Box<dyn SensorProvider>) {
+        self.provider = Some(provider);
+    }
+    
+    /// Detach the current provider, e.g. to swap in an emulated one
+    pub fn take_provider(&mut self) -> Option<Box<dyn SensorProvider>> {
+        self.provider.take()
+    }
+    
+    /// Pull fresh readings from the provider into active sensors and the
+    /// orientation/motion events
+    pub fn poll(&mut self, timestamp: f64) {
+        let Some(provider) = self.provider.as_mut() else { return };
+        if self.accelerometer.is_active() {
+            if let Some(v) = provider.accelerometer() {
+                self.accelerometer.update(v.x, v.y, v.z, timestamp);
+            }
+        }
+        if self.gyroscope.is_active() {
+            if let Some(v) = provider.gyroscope() {
+                self.gyroscope.update(v.x, v.y, v.z, timestamp);
+            }
+        }
+        if self.magnetometer.is_active() {
+            if let Some(v) = provider.magnetometer() {
+                self.magnetometer.update(v.x, v.y, v.z, timestamp);
+            }
+        }
+        if self.ambient_light.is_active() {
+            if let Some(lux) = provider.illuminance() {
+                self.ambient_light.update(lux);
+            }
+        }
+        if let Some(orientation) = provider.orientation() {
+            self.device_orientation = Some(orientation);
+        }
+        if let Some(motion) = provider.motion() {
+            self.device_motion = Some(motion);
+        }
+    }
+    
     pub fn device_orientation(&self) -> Option<DeviceOrientation> {
         self.device_orientation
     }
@@ -245,4 +318,29 @@ mod tests {
         let reading = accel.reading().unwrap();
         assert!((reading.z - 9.8).abs() < 0.001);
     }
+    
+    #[derive(Debug)]
+    struct FixedSensors;
+    
+    impl SensorProvider for FixedSensors {
+        fn accelerometer(&mut self) -> Option<Vec3> {
+            Some(Vec3 { x: 1.0, y: 2.0, z: 3.0 })
+        }
+        fn illuminance(&mut self) -> Option<f64> {
+            Some(250.0)
+        }
+    }
+    
+    #[test]
+    fn test_poll_provider() {
+        let mut sensors = SensorsManager::new();
+        sensors.set_provider(Box::new(FixedSensors));
+        sensors.accelerometer.start();
+        sensors.poll(16.0);
+        
+        assert_eq!(sensors.accelerometer.reading().unwrap().y, 2.0);
+        // Inactive sensors are not fed
+        assert_eq!(sensors.ambient_light.illuminance(), None);
+        assert!(sensors.device_orientation().is_none());
+    }
 }
@@ -13,8 +13,25 @@
 //! - `fetch`: Intercept and serve cached/network responses
 //! - `push`: Handle push notifications
 //! - `sync`: Background sync when online
+//!
+//! ## Navigation preload
+//! With navigation preload on, a navigation into a controlled scope sends
+//! its request with a `Service-Worker-Navigation-Preload` header, and the
+//! response reaches the fetch event as `event.preloadResponse`. Pages turn
+//! it on through `registration.navigationPreload`.
+//!
+//! No worker script runs here: fetch events are not dispatched to a
+//! script handler. The browser answers navigations for the worker as a
+//! cache-first handler would, from the worker's caches, else with the
+//! preload response.
 
 use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+
+use fos_js::{HostApi, HostObject, HostValue, IntoJs, JsError};
+use fos_security::{WorkerKind, WorkerPolicyError};
+
+use crate::security::SecurityManager;
 
 /// Service worker state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -56,6 +73,61 @@ pub struct ServiceWorkerRegistration {
     pub installing: Option<ServiceWorker>,
     pub waiting: Option<ServiceWorker>,
     pub active: Option<ServiceWorker>,
+    /// `registration.navigationPreload`
+    pub navigation_preload: NavigationPreloadState,
+}
+
+/// Header preload requests carry so servers can tell them apart
+pub const NAVIGATION_PRELOAD_HEADER: &str = "Service-Worker-Navigation-Preload";
+
+/// `NavigationPreloadManager.getState()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NavigationPreloadState {
+    pub enabled: bool,
+    /// Value of the `Service-Worker-Navigation-Preload` header
+    pub header_value: String,
+}
+
+impl Default for NavigationPreloadState {
+    fn default() -> Self {
+        Self { enabled: false, header_value: "true".to_string() }
+    }
+}
+
+/// Where a preload's response lands, signalled when it does
+type PreloadSlot = (Mutex<Option<Result<CachedResponse, String>>>, Condvar);
+
+/// `event.preloadResponse`: a navigation's response, settled once the
+/// preload request is answered. Clones share the one response.
+#[derive(Debug, Clone, Default)]
+pub struct PreloadResponse {
+    shared: Arc<PreloadSlot>,
+}
+
+impl PreloadResponse {
+    /// Settle the response; later calls are ignored
+    pub fn settle(&self, result: Result<CachedResponse, String>) {
+        let (slot, settled) = &*self.shared;
+        slot.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert(result);
+        settled.notify_all();
+    }
+
+    /// The response, if it has arrived
+    pub fn try_get(&self) -> Option<Result<CachedResponse, String>> {
+        self.shared.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Wait for the response
+    pub fn wait(&self) -> Result<CachedResponse, String> {
+        let (slot, settled) = &*self.shared;
+        let mut result = slot.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(result) = result.as_ref() {
+                return result.clone();
+            }
+            result = settled.wait(result).unwrap_or_else(|e| e.into_inner());
+        }
+    }
 }
 
 impl ServiceWorkerRegistration {
@@ -84,8 +156,9 @@ pub enum ServiceWorkerEvent {
     Install { worker_id: u64 },
     /// Activate event - claim clients, cleanup
     Activate { worker_id: u64 },
-    /// Fetch event - intercept request
-    Fetch { worker_id: u64, request: FetchRequest },
+    /// Fetch event - intercept request; navigations may come with their
+    /// preload response already loading
+    Fetch { worker_id: u64, request: FetchRequest, preload_response: Option<PreloadResponse> },
     /// Push notification received
     Push { worker_id: u64, data: Vec<u8> },
     /// Background sync
@@ -94,6 +167,28 @@ pub enum ServiceWorkerEvent {
     Message { worker_id: u64, data: String },
 }
 
+/// A navigation into a controlled scope, as `intercept_navigation` hands
+/// it over. Send `preload_request` without holding the worker manager,
+/// then settle the event's `preloadResponse` with its answer.
+#[derive(Debug)]
+pub struct ControlledNavigation {
+    /// Fetch event for the controlling worker
+    pub event: ServiceWorkerEvent,
+    /// Request for `preloadResponse`, when navigation preload is on
+    pub preload_request: Option<FetchRequest>,
+    /// The worker's cached response for the URL
+    pub cached: Option<CachedResponse>,
+}
+
+impl ControlledNavigation {
+    /// Settle the fetch event's `preloadResponse`
+    pub fn settle_preload(&self, result: Result<CachedResponse, String>) {
+        if let ServiceWorkerEvent::Fetch { preload_response: Some(preload), .. } = &self.event {
+            preload.settle(result);
+        }
+    }
+}
+
 /// Fetch request for interception
 #[derive(Debug, Clone)]
 pub struct FetchRequest {
@@ -172,6 +267,7 @@ impl ServiceWorkerContainer {
             installing: Some(worker),
             waiting: None,
             active: None,
+            navigation_preload: NavigationPreloadState::default(),
         };
 
         self.registrations.insert(scope, registration);
@@ -247,12 +343,74 @@ impl ServiceWorkerContainer {
                         mode: RequestMode::Navigate,
                         destination: RequestDestination::Document,
                     },
+                    preload_response: None,
                 });
             }
         }
         None
     }
 
+    /// Start a navigation to `url` in a controlled scope: the fetch event
+    /// for its worker and, when the registration has navigation preload
+    /// on, the preload request to send. `None` leaves the navigation to
+    /// the network.
+    pub fn intercept_navigation(&self, url: &str, headers: HashMap<String, String>) -> Option<ControlledNavigation> {
+        let reg = self.get_registration(url)?;
+        let worker_id = reg.active.as_ref()?.id;
+        let mut request = FetchRequest {
+            url: url.to_string(),
+            method: "GET".to_string(),
+            headers,
+            mode: RequestMode::Navigate,
+            destination: RequestDestination::Document,
+        };
+
+        let preload_request = reg.navigation_preload.enabled.then(|| {
+            let mut preload = request.clone();
+            preload.headers.insert(NAVIGATION_PRELOAD_HEADER.to_string(), reg.navigation_preload.header_value.clone());
+            preload
+        });
+        let preload_response = preload_request.as_ref().map(|_| PreloadResponse::default());
+        // The worker sees the request as the page made it
+        request.headers.remove(NAVIGATION_PRELOAD_HEADER);
+        Some(ControlledNavigation {
+            event: ServiceWorkerEvent::Fetch { worker_id, request, preload_response },
+            preload_request,
+            cached: None,
+        })
+    }
+
+    /// `registration.navigationPreload`, for the registration at `scope`.
+    /// Changing it needs an active worker.
+    fn navigation_preload_mut(&mut self, scope: &str) -> Result<&mut NavigationPreloadState, ServiceWorkerError> {
+        let reg = self.registrations.get_mut(scope).ok_or(ServiceWorkerError::NotFound)?;
+        if reg.active.is_none() {
+            return Err(ServiceWorkerError::InvalidState("registration has no active worker".into()));
+        }
+        Ok(&mut reg.navigation_preload)
+    }
+
+    /// `navigationPreload.enable()` and `disable()`
+    pub fn set_navigation_preload(&mut self, scope: &str, enabled: bool) -> Result<(), ServiceWorkerError> {
+        self.navigation_preload_mut(scope)?.enabled = enabled;
+        Ok(())
+    }
+
+    /// `navigationPreload.setHeaderValue(value)`
+    pub fn set_navigation_preload_header(&mut self, scope: &str, value: &str) -> Result<(), ServiceWorkerError> {
+        // Header values can't hold line breaks or NUL
+        if value.contains(['\r', '\n', '\0']) {
+            return Err(ServiceWorkerError::InvalidHeaderValue);
+        }
+        self.navigation_preload_mut(scope)?.header_value = value.trim_matches([' ', '\t']).to_string();
+        Ok(())
+    }
+
+    /// `navigationPreload.getState()`
+    pub fn navigation_preload_state(&self, scope: &str) -> Option<&NavigationPreloadState> {
+        self.registrations.get(scope).map(|reg| &reg.navigation_preload)
+    }
+
     /// Unregister a service worker
     pub fn unregister(&mut self, scope: &str) -> bool {
         self.registrations.remove(scope).is_some()
@@ -385,6 +543,8 @@ pub enum ServiceWorkerError {
     NetworkError(String),
     NotFound,
     InvalidScope,
+    InvalidState(String),
+    InvalidHeaderValue,
 }
 
 impl std::fmt::Display for ServiceWorkerError {
@@ -394,6 +554,8 @@ impl std::fmt::Display for ServiceWorkerError {
             Self::NetworkError(msg) => write!(f, "Network error: {}", msg),
             Self::NotFound => write!(f, "Service worker not found"),
             Self::InvalidScope => write!(f, "Invalid scope"),
+            Self::InvalidState(msg) => write!(f, "Invalid state: {}", msg),
+            Self::InvalidHeaderValue => write!(f, "Invalid header value"),
         }
     }
 }
@@ -433,6 +595,32 @@ impl ServiceWorkerManager {
         self.container.register(script_url, scope)
     }
 
+    /// Register a service worker after checking its script against the
+    /// page's `worker-src`; the worker's policy container comes from the
+    /// script's response headers
+    pub fn register_with_policy(
+        &mut self,
+        security: &mut SecurityManager,
+        script_url: &str,
+        scope: Option<&str>,
+        origin: &str,
+        headers: &HashMap<String, String>,
+    ) -> Result<u64, ServiceWorkerError> {
+        let policy_error = |e: WorkerPolicyError| ServiceWorkerError::SecurityError(e.to_string());
+        security.check_worker_script(None, WorkerKind::Service, script_url, origin).map_err(policy_error)?;
+        let id = self.container.register(script_url, scope)?;
+        if let Err(e) = security.attach_worker(None, WorkerKind::Service, id, script_url, headers) {
+            let scope = self.container.get_registrations().into_iter()
+                .find(|reg| reg.installing.as_ref().is_some_and(|sw| sw.id == id))
+                .map(|reg| reg.scope.clone());
+            if let Some(scope) = scope {
+                self.container.unregister(&scope);
+            }
+            return Err(policy_error(e));
+        }
+        Ok(id)
+    }
+
     /// Process pending lifecycle events
     pub fn tick(&mut self) -> Vec<ServiceWorkerEvent> {
         self.container.process_events()
@@ -457,6 +645,15 @@ impl ServiceWorkerManager {
         None
     }
 
+    /// A navigation to `url` handed to its controlling worker, with the
+    /// worker's cached response for it. `None` leaves the navigation to
+    /// the network.
+    pub fn intercept_navigation(&self, url: &str, headers: HashMap<String, String>) -> Option<ControlledNavigation> {
+        let mut navigation = self.container.intercept_navigation(url, headers)?;
+        navigation.cached = self.cache_storage.match_all(url).cloned();
+        Some(navigation)
+    }
+
     /// Check if URL can be served offline
     pub fn can_serve_offline(&self, url: &str) -> bool {
         self.cache_storage.match_all(url).is_some()
@@ -468,6 +665,54 @@ impl ServiceWorkerManager {
     }
 }
 
+/// `registration` of the page at `page_url`: the registration of the worker
+/// controlling it, with `navigationPreload.enable()`, `disable()`,
+/// `setHeaderValue(value)` and `getState()`
+pub fn registration_api(workers: &Arc<Mutex<ServiceWorkerManager>>, page_url: &str) -> HostApi {
+    let set_enabled = |enabled: bool| {
+        let (workers, page_url) = (workers.clone(), page_url.to_string());
+        move || update_preload(&workers, &page_url, |container, scope| container.set_navigation_preload(scope, enabled))
+    };
+    let (w, url) = (workers.clone(), page_url.to_string());
+    let set_header_value = move |value: String| {
+        update_preload(&w, &url, |container, scope| container.set_navigation_preload_header(scope, &value))
+    };
+    let (w, url) = (workers.clone(), page_url.to_string());
+    let get_state = move || {
+        let workers = w.lock().unwrap();
+        let state = workers.container().get_registration(&url)
+            .map(|reg| reg.navigation_preload.clone())
+            .unwrap_or_default();
+        Ok(HostValue::object([
+            ("enabled", state.enabled.into_js()),
+            ("headerValue", state.header_value.into_js()),
+        ]))
+    };
+
+    let navigation_preload = HostObject::new()
+        .function("enable", set_enabled(true))
+        .function("disable", set_enabled(false))
+        .function("setHeaderValue", set_header_value)
+        .function("getState", get_state);
+    HostApi::new().object("registration", HostObject::new().object("navigationPreload", navigation_preload))
+}
+
+/// Change the navigation preload of the registration controlling `page_url`
+fn update_preload<F>(workers: &Mutex<ServiceWorkerManager>, page_url: &str, change: F) -> Result<(), JsError>
+where
+    F: FnOnce(&mut ServiceWorkerContainer, &str) -> Result<(), ServiceWorkerError>,
+{
+    let mut workers = workers.lock().unwrap();
+    let container = workers.container_mut();
+    let scope = container.get_registration(page_url)
+        .map(|reg| reg.scope.clone())
+        .ok_or_else(|| JsError::Runtime("InvalidStateError: no service worker registration for this page".to_string()))?;
+    change(container, &scope).map_err(|e| match e {
+        ServiceWorkerError::InvalidHeaderValue => JsError::TypeError(e.to_string()),
+        e => JsError::Runtime(format!("InvalidStateError: {}", e)),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -533,5 +778,40 @@ mod tests {
         let resp = mgr.intercept("/unknown.js", "GET");
         assert!(resp.is_none());
     }
+
+    #[test]
+    fn test_navigation_preload() {
+        let mut mgr = ServiceWorkerManager::new();
+        let id = mgr.register("/sw.js", Some("/")).unwrap();
+        let container = mgr.container_mut();
+        assert!(matches!(container.set_navigation_preload("/", true), Err(ServiceWorkerError::InvalidState(_))));
+        container.on_install_complete(id);
+        container.on_activate_complete(id);
+
+        // Off by default: the worker gets no preload
+        let navigation = container.intercept_navigation("/page", HashMap::new()).unwrap();
+        assert!(navigation.preload_request.is_none());
+        assert!(matches!(navigation.event, ServiceWorkerEvent::Fetch { preload_response: None, .. }));
+
+        container.set_navigation_preload("/", true).unwrap();
+        container.set_navigation_preload_header("/", "v2").unwrap();
+        assert!(container.set_navigation_preload_header("/", "a\r\nb").is_err());
+        assert_eq!(container.navigation_preload_state("/").unwrap().header_value, "v2");
+
+        // The preload request carries the header; the event's request does not
+        let navigation = container.intercept_navigation("/page", HashMap::new()).unwrap();
+        let preload_request = navigation.preload_request.clone().unwrap();
+        assert_eq!(preload_request.headers.get(NAVIGATION_PRELOAD_HEADER).map(String::as_str), Some("v2"));
+        let ServiceWorkerEvent::Fetch { worker_id, request, preload_response: Some(preload) } = &navigation.event else {
+            panic!("expected a fetch event with a preload response");
+        };
+        assert_eq!(*worker_id, id);
+        assert!(!request.headers.contains_key(NAVIGATION_PRELOAD_HEADER));
+        assert!(preload.try_get().is_none());
+        navigation.settle_preload(Ok(CachedResponse::new(200, b"v2".to_vec())));
+        assert_eq!(preload.clone().wait().unwrap().body, b"v2");
+
+        assert!(container.intercept_navigation("https://other.example/", HashMap::new()).is_none());
+    }
 }
 
@@ -0,0 +1,599 @@
+//! Session Restore
+//!
+//! Open tabs, their navigation histories, scroll offsets and unsubmitted
+//! form values are written to a snapshot file periodically and on exit.
+//! Changes between snapshots are appended to a journal whose records each
+//! carry a checksum, so a crash mid-write loses only the torn record; on
+//! startup the journal is replayed over the last complete snapshot.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use fos_dom::{DomTree, NodeId};
+
+use crate::scroll_restoration::ScrollOffset;
+use crate::tab::TabId;
+
+/// Snapshot file name in the session directory
+const SNAPSHOT_FILE: &str = "session";
+/// Journal file name in the session directory
+const JOURNAL_FILE: &str = "session.journal";
+/// Snapshot format version
+const FORMAT_VERSION: u32 = 1;
+
+/// Saved value of a form control
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    /// Text of an input or textarea
+    Text(String),
+    /// Checkedness of a checkbox or radio button
+    Checked(bool),
+}
+
+/// Unsubmitted value of one form control
+///
+/// Controls are keyed by `#id`, or by `name` and its occurrence among
+/// controls of that name (`q:0`), which survive a reload of the same page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormFieldState {
+    pub key: String,
+    pub value: FieldValue,
+}
+
+/// Saved state of one tab
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TabState {
+    /// Tab ID in the session that saved it
+    pub id: TabId,
+    /// Current URL
+    pub url: String,
+    /// Page title
+    pub title: String,
+    /// Back entries, oldest first
+    pub back: Vec<String>,
+    /// Forward entries, next first
+    pub forward: Vec<String>,
+    /// Root scroller offset
+    pub scroll: ScrollOffset,
+    /// Unsubmitted form values
+    pub form_fields: Vec<FormFieldState>,
+}
+
+/// State a restored tab applies once its page loads again
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PendingRestore {
+    pub scroll: ScrollOffset,
+    pub form_fields: Vec<FormFieldState>,
+}
+
+/// Saved state of the browser window
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionState {
+    /// Tabs in strip order
+    pub tabs: Vec<TabState>,
+    /// ID of the active tab
+    pub active: Option<TabId>,
+}
+
+/// A change journaled between snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionChange {
+    /// Add or replace a tab, at a position in the strip
+    UpdateTab { index: usize, tab: TabState },
+    /// Close a tab
+    CloseTab(TabId),
+    /// Switch the active tab
+    Activate(Option<TabId>),
+}
+
+impl SessionState {
+    /// Apply a journaled change
+    pub fn apply(&mut self, change: SessionChange) {
+        match change {
+            SessionChange::UpdateTab { index, tab } => {
+                self.tabs.retain(|t| t.id != tab.id);
+                let index = index.min(self.tabs.len());
+                self.tabs.insert(index, tab);
+            }
+            SessionChange::CloseTab(id) => {
+                self.tabs.retain(|t| t.id != id);
+                if self.active == Some(id) {
+                    self.active = None;
+                }
+            }
+            SessionChange::Activate(id) => self.active = id,
+        }
+    }
+    
+    /// Changes that turn this state into `current`
+    pub fn diff(&self, current: &SessionState) -> Vec<SessionChange> {
+        let mut changes = Vec::new();
+        for tab in &self.tabs {
+            if !current.tabs.iter().any(|t| t.id == tab.id) {
+                changes.push(SessionChange::CloseTab(tab.id));
+            }
+        }
+        let mut order: Vec<TabId> = self.tabs.iter()
+            .map(|t| t.id)
+            .filter(|id| current.tabs.iter().any(|t| t.id == *id))
+            .collect();
+        for (index, tab) in current.tabs.iter().enumerate() {
+            let unchanged = order.get(index) == Some(&tab.id)
+                && self.tabs.iter().any(|t| t == tab);
+            if !unchanged {
+                order.retain(|id| *id != tab.id);
+                order.insert(index.min(order.len()), tab.id);
+                changes.push(SessionChange::UpdateTab { index, tab: tab.clone() });
+            }
+        }
+        if self.active != current.active {
+            changes.push(SessionChange::Activate(current.active));
+        }
+        changes
+    }
+    
+    /// Serialize as a snapshot
+    pub fn serialize(&self) -> String {
+        let mut out = format!("S\t{}\n", FORMAT_VERSION);
+        if let Some(active) = self.active {
+            out.push_str(&format!("A\t{}\n", active));
+        }
+        for tab in &self.tabs {
+            write_tab(&mut out, tab);
+        }
+        out
+    }
+    
+    /// Parse a snapshot, `None` for an unknown format
+    pub fn parse(data: &str) -> Option<Self> {
+        let mut lines = data.lines();
+        let version: u32 = lines.next()?.strip_prefix("S\t")?.parse().ok()?;
+        if version != FORMAT_VERSION {
+            return None;
+        }
+        let mut state = SessionState::default();
+        let mut tab_lines = String::new();
+        for line in lines {
+            if let Some(id) = line.strip_prefix("A\t") {
+                state.active = id.parse().ok();
+            } else {
+                tab_lines.push_str(line);
+                tab_lines.push('\n');
+            }
+        }
+        state.tabs = read_tabs(&tab_lines);
+        Some(state)
+    }
+}
+
+impl SessionChange {
+    /// Encode as one journal line, without the checksum
+    fn encode(&self) -> String {
+        match self {
+            SessionChange::UpdateTab { index, tab } => {
+                let mut block = String::new();
+                write_tab(&mut block, tab);
+                format!("U\t{}\t{}", index, escape(&block))
+            }
+            SessionChange::CloseTab(id) => format!("C\t{}", id),
+            SessionChange::Activate(Some(id)) => format!("A\t{}", id),
+            SessionChange::Activate(None) => "A\t".to_string(),
+        }
+    }
+    
+    fn decode(record: &str) -> Option<Self> {
+        let mut parts = record.splitn(3, '\t');
+        match (parts.next()?, parts.next()?) {
+            ("U", index) => {
+                let block = unescape(parts.next()?);
+                let tab = read_tabs(&block).into_iter().next()?;
+                Some(SessionChange::UpdateTab { index: index.parse().ok()?, tab })
+            }
+            ("C", id) => Some(SessionChange::CloseTab(id.parse().ok()?)),
+            ("A", "") => Some(SessionChange::Activate(None)),
+            ("A", id) => Some(SessionChange::Activate(Some(id.parse().ok()?))),
+            _ => None,
+        }
+    }
+}
+
+/// Write one tab as a block of lines
+fn write_tab(out: &mut String, tab: &TabState) {
+    out.push_str(&format!(
+        "T\t{}\t{}\t{}\t{}\t{}\n",
+        tab.id, escape(&tab.url), escape(&tab.title), tab.scroll.x, tab.scroll.y
+    ));
+    for url in &tab.back {
+        out.push_str(&format!("B\t{}\n", escape(url)));
+    }
+    for url in &tab.forward {
+        out.push_str(&format!("F\t{}\n", escape(url)));
+    }
+    for field in &tab.form_fields {
+        match &field.value {
+            FieldValue::Text(text) => out.push_str(&format!("I\t{}\ttext\t{}\n", escape(&field.key), escape(text))),
+            FieldValue::Checked(checked) => out.push_str(&format!("I\t{}\tchecked\t{}\n", escape(&field.key), *checked as u8)),
+        }
+    }
+}
+
+/// Read tab blocks, skipping malformed lines
+fn read_tabs(data: &str) -> Vec<TabState> {
+    let mut tabs: Vec<TabState> = Vec::new();
+    for line in data.lines() {
+        let parts: Vec<&str> = line.split('\t').collect();
+        match parts.as_slice() {
+            ["T", id, url, title, x, y] => tabs.push(TabState {
+                id: id.parse().unwrap_or(0),
+                url: unescape(url),
+                title: unescape(title),
+                scroll: ScrollOffset::new(x.parse().unwrap_or(0.0), y.parse().unwrap_or(0.0)),
+                ..Default::default()
+            }),
+            ["B", url] => if let Some(tab) = tabs.last_mut() {
+                tab.back.push(unescape(url));
+            },
+            ["F", url] => if let Some(tab) = tabs.last_mut() {
+                tab.forward.push(unescape(url));
+            },
+            ["I", key, kind, value] => if let Some(tab) = tabs.last_mut() {
+                let value = match *kind {
+                    "checked" => FieldValue::Checked(*value == "1"),
+                    _ => FieldValue::Text(unescape(value)),
+                };
+                tab.form_fields.push(FormFieldState { key: unescape(key), value });
+            },
+            _ => {}
+        }
+    }
+    tabs
+}
+
+/// Escape tabs, newlines and backslashes so a field stays on one line
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+    out
+}
+
+/// FNV-1a checksum of a journal record
+fn checksum(record: &str) -> u32 {
+    record.bytes().fold(0x811c_9dc5, |hash, b| (hash ^ b as u32).wrapping_mul(0x0100_0193))
+}
+
+/// On-disk session: a snapshot plus a journal of later changes
+#[derive(Debug)]
+pub struct SessionStore {
+    /// Directory holding the session files
+    dir: PathBuf,
+    /// Open journal, appended to between snapshots
+    journal: Option<File>,
+    /// Time between snapshots
+    snapshot_interval: Duration,
+    /// When the last snapshot was written
+    last_snapshot: Instant,
+}
+
+impl SessionStore {
+    /// Create a store in a directory
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            journal: None,
+            snapshot_interval: Duration::from_secs(30),
+            last_snapshot: Instant::now(),
+        }
+    }
+    
+    /// Default session directory, under the user's home
+    pub fn default_dir() -> Option<PathBuf> {
+        std::env::var("HOME")
+            .ok()
+            .map(|h| PathBuf::from(h).join(".fos-browser"))
+    }
+    
+    /// Set the time between periodic snapshots
+    pub fn with_snapshot_interval(mut self, interval: Duration) -> Self {
+        self.snapshot_interval = interval;
+        self
+    }
+    
+    /// Directory holding the session files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    
+    /// Load the saved session: the last snapshot with the journal replayed
+    ///
+    /// Replay stops at the first record with a bad checksum, which is where
+    /// a crash cut the journal short.
+    pub fn load(&self) -> Option<SessionState> {
+        let snapshot = fs::read_to_string(self.dir.join(SNAPSHOT_FILE)).ok();
+        let journal = fs::read_to_string(self.dir.join(JOURNAL_FILE)).unwrap_or_default();
+        let mut state = match snapshot {
+            Some(data) => SessionState::parse(&data)?,
+            None if journal.is_empty() => return None,
+            None => SessionState::default(),
+        };
+        for line in journal.lines() {
+            let Some((sum, record)) = line.split_once('\t') else { break };
+            if u32::from_str_radix(sum, 16).ok() != Some(checksum(record)) {
+                break;
+            }
+            let Some(change) = SessionChange::decode(record) else { break };
+            state.apply(change);
+        }
+        (!state.tabs.is_empty()).then_some(state)
+    }
+    
+    /// Append changes to the journal
+    pub fn record(&mut self, changes: &[SessionChange]) -> io::Result<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        if self.journal.is_none() {
+            fs::create_dir_all(&self.dir)?;
+            let file = OpenOptions::new().create(true).append(true).open(self.dir.join(JOURNAL_FILE))?;
+            self.journal = Some(file);
+        }
+        let mut data = String::new();
+        for change in changes {
+            let record = change.encode();
+            data.push_str(&format!("{:08x}\t{}\n", checksum(&record), record));
+        }
+        let journal = self.journal.as_mut().expect("journal opened above");
+        journal.write_all(data.as_bytes())?;
+        journal.sync_data()
+    }
+    
+    /// Whether a periodic snapshot is due
+    pub fn snapshot_due(&self) -> bool {
+        self.last_snapshot.elapsed() >= self.snapshot_interval
+    }
+    
+    /// Write a snapshot and start a new journal
+    ///
+    /// The snapshot goes to a temporary file that replaces the old one only
+    /// once it is on disk, so a crash leaves one complete snapshot or the
+    /// other.
+    pub fn snapshot(&mut self, state: &SessionState) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let tmp = self.dir.join(format!("{}.tmp", SNAPSHOT_FILE));
+        let mut file = File::create(&tmp)?;
+        file.write_all(state.serialize().as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp, self.dir.join(SNAPSHOT_FILE))?;
+        
+        self.journal = None;
+        File::create(self.dir.join(JOURNAL_FILE))?;
+        self.last_snapshot = Instant::now();
+        Ok(())
+    }
+    
+    /// Delete the saved session
+    pub fn clear(&mut self) {
+        self.journal = None;
+        let _ = fs::remove_file(self.dir.join(SNAPSHOT_FILE));
+        let _ = fs::remove_file(self.dir.join(JOURNAL_FILE));
+    }
+}
+
+/// Current values of a document's text inputs, textareas, checkboxes and
+/// radio buttons
+///
+/// Password, hidden and file inputs are never saved.
+pub fn capture_form_fields(tree: &DomTree) -> Vec<FormFieldState> {
+    let mut fields = Vec::new();
+    let mut seen: Vec<(String, usize)> = Vec::new();
+    for (node, tag) in form_controls(tree) {
+        let Some(value) = control_value(tree, node, &tag) else { continue };
+        let Some(key) = control_key(tree, node, &mut seen) else { continue };
+        fields.push(FormFieldState { key, value });
+    }
+    fields
+}
+
+/// Refill form controls with saved values
+pub fn restore_form_fields(tree: &mut DomTree, fields: &[FormFieldState]) {
+    let mut seen: Vec<(String, usize)> = Vec::new();
+    for (node, tag) in form_controls(tree) {
+        let Some(key) = control_key(tree, node, &mut seen) else { continue };
+        let Some(field) = fields.iter().find(|f| f.key == key) else { continue };
+        match (&field.value, tag.as_str()) {
+            (FieldValue::Text(text), "textarea") => {
+                let text_node = tree.children(node).find(|(_, n)| n.is_text()).map(|(id, _)| id);
+                match text_node {
+                    Some(text_node) => tree.set_text(text_node, text),
+                    None => {
+                        let text_node = tree.create_text(text);
+                        tree.append_child(node, text_node);
+                    }
+                }
+            }
+            (FieldValue::Text(text), _) => tree.set_attribute(node, "value", text),
+            (FieldValue::Checked(true), _) => tree.set_attribute(node, "checked", ""),
+            (FieldValue::Checked(false), _) => tree.remove_attribute(node, "checked"),
+        }
+    }
+}
+
+/// Input and textarea elements in document order
+fn form_controls(tree: &DomTree) -> Vec<(NodeId, String)> {
+    let mut controls = Vec::new();
+    let mut stack = vec![tree.root()];
+    while let Some(id) = stack.pop() {
+        let children: Vec<NodeId> = tree.children(id).map(|(child, _)| child).collect();
+        stack.extend(children.into_iter().rev());
+        let Some(elem) = tree.get(id).and_then(|n| n.as_element()) else { continue };
+        let tag = tree.resolve(elem.name.local).to_ascii_lowercase();
+        if tag == "input" || tag == "textarea" {
+            controls.push((id, tag));
+        }
+    }
+    controls
+}
+
+fn attribute<'a>(tree: &'a DomTree, node: NodeId, name: &str) -> Option<&'a str> {
+    let elem = tree.get(node)?.as_element()?;
+    elem.attrs.iter()
+        .find(|a| tree.resolve(a.name.local).eq_ignore_ascii_case(name))
+        .map(|a| a.value.as_str())
+}
+
+fn control_value(tree: &DomTree, node: NodeId, tag: &str) -> Option<FieldValue> {
+    if tag == "textarea" {
+        let text: String = tree.children(node).filter_map(|(_, n)| n.as_text()).collect();
+        return Some(FieldValue::Text(text));
+    }
+    let kind = attribute(tree, node, "type").unwrap_or("text").to_ascii_lowercase();
+    match kind.as_str() {
+        "password" | "hidden" | "file" | "submit" | "reset" | "button" | "image" => None,
+        "checkbox" | "radio" => Some(FieldValue::Checked(attribute(tree, node, "checked").is_some())),
+        _ => Some(FieldValue::Text(attribute(tree, node, "value").unwrap_or("").to_string())),
+    }
+}
+
+fn control_key(tree: &DomTree, node: NodeId, seen: &mut Vec<(String, usize)>) -> Option<String> {
+    if let Some(id) = attribute(tree, node, "id").filter(|id| !id.is_empty()) {
+        return Some(format!("#{}", id));
+    }
+    let name = attribute(tree, node, "name").filter(|n| !n.is_empty())?;
+    let occurrence = match seen.iter_mut().find(|(n, _)| n == name) {
+        Some((_, count)) => {
+            *count += 1;
+            *count
+        }
+        None => {
+            seen.push((name.to_string(), 0));
+            0
+        }
+    };
+    Some(format!("{}:{}", name, occurrence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn sample() -> SessionState {
+        SessionState {
+            tabs: vec![
+                TabState {
+                    id: 1,
+                    url: "https://example.com/b".into(),
+                    title: "B\tpage".into(),
+                    back: vec!["https://example.com/a".into()],
+                    forward: vec!["https://example.com/c".into()],
+                    scroll: ScrollOffset::new(0.0, 420.5),
+                    form_fields: vec![
+                        FormFieldState { key: "#q".into(), value: FieldValue::Text("line 1\nline 2".into()) },
+                        FormFieldState { key: "agree:0".into(), value: FieldValue::Checked(true) },
+                    ],
+                },
+                TabState { id: 2, url: "about:blank".into(), title: "New Tab".into(), ..Default::default() },
+            ],
+            active: Some(1),
+        }
+    }
+    
+    #[test]
+    fn test_serialize_roundtrip() {
+        let state = sample();
+        assert_eq!(SessionState::parse(&state.serialize()), Some(state));
+        assert_eq!(SessionState::parse("S\t99\n"), None);
+    }
+    
+    #[test]
+    fn test_journal_replay() {
+        let dir = std::env::temp_dir().join(format!("fos-session-test-{}", std::process::id()));
+        let mut store = SessionStore::new(dir.clone());
+        let mut state = sample();
+        store.snapshot(&state).unwrap();
+        
+        let mut next = state.clone();
+        next.tabs[0].scroll = ScrollOffset::new(0.0, 900.0);
+        next.tabs.remove(1);
+        next.tabs.push(TabState { id: 3, url: "https://rust-lang.org/".into(), ..Default::default() });
+        next.active = Some(3);
+        let changes = state.diff(&next);
+        store.record(&changes).unwrap();
+        state = next;
+        assert_eq!(store.load(), Some(state.clone()));
+        
+        // A torn record at the end is dropped
+        let mut journal = OpenOptions::new().append(true).open(dir.join(JOURNAL_FILE)).unwrap();
+        journal.write_all(b"0badf00d\tC\t1\nU\t0\tT").unwrap();
+        assert_eq!(store.load(), Some(state));
+        
+        store.clear();
+        assert_eq!(store.load(), None);
+        let _ = fs::remove_dir_all(dir);
+    }
+    
+    #[test]
+    fn test_form_fields() {
+        let mut tree = DomTree::new();
+        let form = tree.create_element("form");
+        tree.append_child(tree.root(), form);
+        let search = tree.create_element("input");
+        tree.set_attribute(search, "id", "q");
+        tree.set_attribute(search, "value", "rust");
+        let agree = tree.create_element("input");
+        tree.set_attribute(agree, "type", "checkbox");
+        tree.set_attribute(agree, "name", "agree");
+        tree.set_attribute(agree, "checked", "");
+        let password = tree.create_element("input");
+        tree.set_attribute(password, "type", "password");
+        tree.set_attribute(password, "name", "pw");
+        tree.set_attribute(password, "value", "secret");
+        let notes = tree.create_element("textarea");
+        tree.set_attribute(notes, "name", "notes");
+        for node in [search, agree, password, notes] {
+            tree.append_child(form, node);
+        }
+        let text = tree.create_text("draft");
+        tree.append_child(notes, text);
+        
+        let fields = capture_form_fields(&tree);
+        assert_eq!(fields, vec![
+            FormFieldState { key: "#q".into(), value: FieldValue::Text("rust".into()) },
+            FormFieldState { key: "agree:0".into(), value: FieldValue::Checked(true) },
+            FormFieldState { key: "notes:0".into(), value: FieldValue::Text("draft".into()) },
+        ]);
+        
+        tree.set_attribute(search, "value", "");
+        tree.remove_attribute(agree, "checked");
+        tree.set_text(text, "");
+        restore_form_fields(&mut tree, &fields);
+        assert_eq!(capture_form_fields(&tree), fields);
+    }
+}
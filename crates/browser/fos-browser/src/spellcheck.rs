@@ -0,0 +1,585 @@
+//! Spellchecking
+//!
+//! Checks editable text against Hunspell dictionaries. Misspelled ranges are
+//! underlined by the renderer, and suggestions feed the context menu.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use fos_dom::{DomTree, NodeId};
+
+/// Where system Hunspell dictionaries are installed
+const DICTIONARY_DIRS: &[&str] = &[
+    "/usr/share/hunspell",
+    "/usr/share/myspell",
+    "/usr/share/myspell/dicts",
+    "/Library/Spelling",
+];
+
+/// How affix flags are written in a dictionary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagMode {
+    /// One character per flag
+    Char,
+    /// Two characters per flag (`FLAG long`)
+    Long,
+    /// Comma-separated numbers (`FLAG num`)
+    Num,
+}
+
+impl FlagMode {
+    fn split(self, flags: &str) -> Vec<String> {
+        match self {
+            Self::Char => flags.chars().map(String::from).collect(),
+            Self::Long => flags.chars().collect::<Vec<_>>().chunks(2).map(|c| c.iter().collect()).collect(),
+            Self::Num => flags.split(',').map(str::trim).filter(|f| !f.is_empty()).map(String::from).collect(),
+        }
+    }
+}
+
+/// One character of an affix condition
+#[derive(Debug, Clone)]
+enum ConditionChar {
+    Any,
+    Char(char),
+    /// `[abc]`, or `[^abc]` when negated
+    Set(Vec<char>, bool),
+}
+
+impl ConditionChar {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Char(expected) => c == *expected,
+            Self::Set(chars, negated) => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+fn parse_condition(condition: &str) -> Vec<ConditionChar> {
+    let mut parsed = Vec::new();
+    let mut chars = condition.chars();
+    while let Some(c) = chars.next() {
+        parsed.push(match c {
+            '.' => ConditionChar::Any,
+            '[' => {
+                let mut set: Vec<char> = chars.by_ref().take_while(|&c| c != ']').collect();
+                let negated = set.first() == Some(&'^');
+                if negated {
+                    set.remove(0);
+                }
+                ConditionChar::Set(set, negated)
+            }
+            c => ConditionChar::Char(c),
+        });
+    }
+    parsed
+}
+
+/// A prefix or suffix rule
+#[derive(Debug, Clone)]
+struct AffixRule {
+    strip: String,
+    add: String,
+    condition: Vec<ConditionChar>,
+}
+
+impl AffixRule {
+    fn apply_suffix(&self, word: &str) -> Option<String> {
+        let chars: Vec<char> = word.chars().collect();
+        if chars.len() < self.condition.len() || !word.ends_with(&self.strip) || word.len() == self.strip.len() {
+            return None;
+        }
+        let tail = &chars[chars.len() - self.condition.len()..];
+        if !self.condition.iter().zip(tail).all(|(cond, &c)| cond.matches(c)) {
+            return None;
+        }
+        Some(format!("{}{}", &word[..word.len() - self.strip.len()], self.add))
+    }
+    
+    fn apply_prefix(&self, word: &str) -> Option<String> {
+        if word.chars().count() < self.condition.len() || !word.starts_with(&self.strip) || word.len() == self.strip.len() {
+            return None;
+        }
+        if !self.condition.iter().zip(word.chars()).all(|(cond, c)| cond.matches(c)) {
+            return None;
+        }
+        Some(format!("{}{}", self.add, &word[self.strip.len()..]))
+    }
+}
+
+/// Rules sharing one affix flag
+#[derive(Debug, Clone)]
+struct AffixGroup {
+    /// Combines with affixes of the other kind
+    cross_product: bool,
+    rules: Vec<AffixRule>,
+}
+
+/// Word list for one language, expanded from a Hunspell `.aff`/`.dic` pair
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    lang: String,
+    words: HashSet<String>,
+    /// Characters tried when suggesting, most common first
+    try_chars: Vec<char>,
+    /// Common misspellings and their replacements
+    replacements: Vec<(String, String)>,
+}
+
+impl Dictionary {
+    /// Parse Hunspell affix and dictionary files
+    pub fn from_hunspell(lang: &str, aff: &str, dic: &str) -> Self {
+        let mut dictionary = Self { lang: lang.to_string(), ..Self::default() };
+        let mut flag_mode = FlagMode::Char;
+        let mut prefixes: HashMap<String, AffixGroup> = HashMap::new();
+        let mut suffixes: HashMap<String, AffixGroup> = HashMap::new();
+        
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["FLAG", mode, ..] => {
+                    flag_mode = match *mode {
+                        "long" => FlagMode::Long,
+                        "num" => FlagMode::Num,
+                        _ => FlagMode::Char,
+                    };
+                }
+                ["TRY", chars, ..] => dictionary.try_chars = chars.chars().collect(),
+                ["REP", from, to, ..] => {
+                    dictionary.replacements.push((from.replace('_', " "), to.replace('_', " ")));
+                }
+                [kind @ ("PFX" | "SFX"), flag, rest @ ..] => {
+                    let groups = if *kind == "PFX" { &mut prefixes } else { &mut suffixes };
+                    match rest {
+                        // Header: cross product and rule count
+                        [cross @ ("Y" | "N"), count] if count.parse::<usize>().is_ok() => {
+                            groups.insert(flag.to_string(), AffixGroup { cross_product: *cross == "Y", rules: Vec::new() });
+                        }
+                        [strip, add, condition @ ..] => {
+                            // Continuation flags after `/` are not expanded
+                            let add = add.split('/').next().unwrap_or_default();
+                            let rule = AffixRule {
+                                strip: if *strip == "0" { String::new() } else { strip.to_string() },
+                                add: if add == "0" { String::new() } else { add.to_string() },
+                                condition: parse_condition(condition.first().copied().unwrap_or(".")),
+                            };
+                            if let Some(group) = groups.get_mut(*flag) {
+                                group.rules.push(rule);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        
+        let mut lines = dic.lines().peekable();
+        // The first line is the approximate word count
+        if lines.peek().is_some_and(|l| l.trim().parse::<usize>().is_ok()) {
+            lines.next();
+        }
+        for line in lines {
+            // Morphological fields follow the word after whitespace
+            let Some(entry) = line.split_whitespace().next() else { continue };
+            let (word, flags) = match entry.split_once('/') {
+                Some((word, flags)) => (word, flag_mode.split(flags)),
+                None => (entry, Vec::new()),
+            };
+            dictionary.words.insert(word.to_string());
+            
+            let mut suffixed = Vec::new();
+            for group in flags.iter().filter_map(|f| suffixes.get(f)) {
+                for form in group.rules.iter().filter_map(|r| r.apply_suffix(word)) {
+                    if group.cross_product {
+                        suffixed.push(form.clone());
+                    }
+                    dictionary.words.insert(form);
+                }
+            }
+            for group in flags.iter().filter_map(|f| prefixes.get(f)) {
+                for rule in &group.rules {
+                    dictionary.words.extend(rule.apply_prefix(word));
+                    if group.cross_product {
+                        dictionary.words.extend(suffixed.iter().filter_map(|s| rule.apply_prefix(s)));
+                    }
+                }
+            }
+        }
+        
+        dictionary
+    }
+    
+    /// Load `<lang>.aff` and `<lang>.dic` from a directory
+    pub fn load(dir: &Path, lang: &str) -> io::Result<Self> {
+        let aff = fs::read_to_string(dir.join(format!("{}.aff", lang)))?;
+        let dic = fs::read_to_string(dir.join(format!("{}.dic", lang)))?;
+        Ok(Self::from_hunspell(lang, &aff, &dic))
+    }
+    
+    /// Load the system dictionary for a language, if one is installed
+    pub fn find(lang: &str) -> Option<Self> {
+        DICTIONARY_DIRS.iter().find_map(|dir| Self::load(Path::new(dir), lang).ok())
+    }
+    
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+    
+    /// Number of word forms
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+    
+    /// Whether the word is spelled correctly; capitalized and all-caps
+    /// forms of lowercase entries are accepted
+    pub fn contains(&self, word: &str) -> bool {
+        let word = word.replace('\u{2019}', "'");
+        if self.words.contains(&word) {
+            return true;
+        }
+        let lower = word.to_lowercase();
+        let capitalized = word.chars().next().is_some_and(char::is_uppercase)
+            && word.chars().skip(1).all(|c| !c.is_uppercase());
+        let all_caps = word.chars().all(|c| !c.is_lowercase());
+        if capitalized {
+            self.words.contains(&lower)
+        } else if all_caps {
+            self.words.contains(&lower) || self.words.contains(&capitalize(&lower))
+        } else {
+            false
+        }
+    }
+}
+
+/// A misspelled word in checked text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Misspelling {
+    /// Byte range of the word in the text
+    pub range: Range<usize>,
+    pub word: String,
+}
+
+/// Context menu entries for a misspelled word
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpellcheckMenu {
+    pub misspelling: Misspelling,
+    pub suggestions: Vec<String>,
+}
+
+/// Choice made in the spellcheck context menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpellcheckAction {
+    /// Replace the word with a suggestion
+    Replace(String),
+    /// Accept the word from now on
+    AddToDictionary,
+    /// Accept the word for this session
+    Ignore,
+}
+
+/// Suggestions offered in the context menu
+const MENU_SUGGESTIONS: usize = 5;
+
+/// Checks words against loaded dictionaries; clones share added and
+/// ignored words
+#[derive(Debug, Clone, Default)]
+pub struct SpellChecker {
+    dictionaries: Vec<Arc<Dictionary>>,
+    added: Arc<RwLock<HashSet<String>>>,
+    ignored: Arc<RwLock<HashSet<String>>>,
+}
+
+impl SpellChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Check against the system dictionaries of these languages
+    pub fn for_languages(langs: &[&str]) -> Self {
+        let mut checker = Self::new();
+        for lang in langs {
+            match Dictionary::find(lang) {
+                Some(dictionary) => checker = checker.with_dictionary(dictionary),
+                None => log::debug!("No spellcheck dictionary for {}", lang),
+            }
+        }
+        checker
+    }
+    
+    /// Check against the dictionary of the user's locale (`LANG`), falling
+    /// back to US English
+    pub fn for_system_language() -> Self {
+        let lang = std::env::var("LANG").ok()
+            .and_then(|l| l.split('.').next().map(String::from))
+            .filter(|l| l.contains('_'))
+            .unwrap_or_else(|| "en_US".to_string());
+        Self::for_languages(&[&lang])
+    }
+    
+    pub fn with_dictionary(mut self, dictionary: Dictionary) -> Self {
+        self.dictionaries.push(Arc::new(dictionary));
+        self
+    }
+    
+    /// Whether any dictionary is loaded
+    pub fn is_enabled(&self) -> bool {
+        !self.dictionaries.is_empty()
+    }
+    
+    /// Whether a word is spelled correctly
+    pub fn check_word(&self, word: &str) -> bool {
+        self.added.read().unwrap().contains(word)
+            || self.ignored.read().unwrap().contains(word)
+            || self.dictionaries.iter().any(|d| d.contains(word))
+    }
+    
+    /// Misspelled words in a run of text
+    pub fn check(&self, text: &str) -> Vec<Misspelling> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+        words(text)
+            .filter(|range| !self.check_word(&text[range.clone()]))
+            .map(|range| Misspelling { word: text[range.clone()].to_string(), range })
+            .collect()
+    }
+    
+    /// Correctly spelled words one edit away, best first
+    pub fn suggest(&self, word: &str, limit: usize) -> Vec<String> {
+        let mut suggestions: Vec<String> = Vec::new();
+        let push = |candidate: String, suggestions: &mut Vec<String>| {
+            if candidate != word && !suggestions.contains(&candidate) && candidate.split(' ').all(|w| self.check_word(w)) {
+                suggestions.push(candidate);
+            }
+        };
+        
+        let try_chars: Vec<char> = self.dictionaries.iter()
+            .flat_map(|d| d.try_chars.iter().copied())
+            .collect();
+        let chars: Vec<char> = word.chars().collect();
+        let join = |chars: &[char]| chars.iter().collect::<String>();
+        
+        // Known misspellings
+        for dictionary in &self.dictionaries {
+            for (from, to) in &dictionary.replacements {
+                for (at, _) in word.match_indices(from.as_str()) {
+                    push(format!("{}{}{}", &word[..at], to, &word[at + from.len()..]), &mut suggestions);
+                }
+            }
+        }
+        push(word.to_lowercase(), &mut suggestions);
+        // Swapped neighbours
+        for i in 1..chars.len() {
+            let mut swapped = chars.clone();
+            swapped.swap(i - 1, i);
+            push(join(&swapped), &mut suggestions);
+        }
+        // Wrong character
+        for i in 0..chars.len() {
+            for &c in &try_chars {
+                let mut replaced = chars.clone();
+                replaced[i] = c;
+                push(join(&replaced), &mut suggestions);
+            }
+        }
+        // Extra character
+        for i in 0..chars.len() {
+            let mut removed = chars.clone();
+            removed.remove(i);
+            push(join(&removed), &mut suggestions);
+        }
+        // Missing character
+        for i in 0..=chars.len() {
+            for &c in &try_chars {
+                let mut inserted = chars.clone();
+                inserted.insert(i, c);
+                push(join(&inserted), &mut suggestions);
+            }
+        }
+        // Missing space
+        for i in 1..chars.len() {
+            push(format!("{} {}", join(&chars[..i]), join(&chars[i..])), &mut suggestions);
+        }
+        
+        // Keep the capitalization of the misspelled word
+        if chars.first().is_some_and(|c| c.is_uppercase()) {
+            for suggestion in &mut suggestions {
+                *suggestion = capitalize(suggestion);
+            }
+            suggestions.dedup();
+        }
+        suggestions.truncate(limit);
+        suggestions
+    }
+    
+    /// Menu for the misspelled word at byte `offset` of `text`
+    pub fn context_menu(&self, text: &str, offset: usize) -> Option<SpellcheckMenu> {
+        let misspelling = self.check(text)
+            .into_iter()
+            .find(|m| m.range.start <= offset && offset <= m.range.end)?;
+        let suggestions = self.suggest(&misspelling.word, MENU_SUGGESTIONS);
+        Some(SpellcheckMenu { misspelling, suggestions })
+    }
+    
+    /// Carry out a context menu choice on the text the menu was built for
+    pub fn apply(&self, text: &mut String, menu: &SpellcheckMenu, action: SpellcheckAction) {
+        match action {
+            SpellcheckAction::Replace(word) => text.replace_range(menu.misspelling.range.clone(), &word),
+            SpellcheckAction::AddToDictionary => {
+                self.added.write().unwrap().insert(menu.misspelling.word.clone());
+            }
+            SpellcheckAction::Ignore => {
+                self.ignored.write().unwrap().insert(menu.misspelling.word.clone());
+            }
+        }
+    }
+}
+
+/// Byte ranges of the words worth checking in `text`: runs of letters with
+/// inner apostrophes, skipping anything with digits, URLs and addresses
+fn words(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let is_apostrophe = |c: char| c == '\'' || c == '\u{2019}';
+    let base = text.as_ptr() as usize;
+    text.split_whitespace()
+        .filter(|chunk| !chunk.contains('@') && !chunk.contains("://") && !chunk.starts_with("www."))
+        .flat_map(move |chunk| {
+            let offset = chunk.as_ptr() as usize - base;
+            let mut ranges = Vec::new();
+            let mut start = None;
+            let mut chars = chunk.char_indices().peekable();
+            while let Some((i, c)) = chars.next() {
+                let next_is_word = chars.peek().is_some_and(|&(_, n)| n.is_alphanumeric());
+                let in_word = c.is_alphanumeric() || (is_apostrophe(c) && start.is_some() && next_is_word);
+                match (in_word, start) {
+                    (true, None) => start = Some(i),
+                    (false, Some(s)) => {
+                        ranges.push(offset + s..offset + i);
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(s) = start {
+                ranges.push(offset + s..offset + chunk.len());
+            }
+            ranges
+        })
+        .filter(move |range| !text[range.clone()].chars().any(|c| c.is_numeric()))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Whether text in `node` is spellchecked: editable content (text fields,
+/// textareas and `contenteditable`), unless the nearest `spellcheck`
+/// attribute turns it off
+pub fn spellcheck_enabled(tree: &DomTree, node: NodeId) -> bool {
+    let mut editable = None;
+    let mut spellcheck = None;
+    let mut id = node;
+    while id.is_valid() && (editable.is_none() || spellcheck.is_none()) {
+        let Some(current) = tree.get(id) else { break };
+        if let Some(element) = current.as_element() {
+            let attr = |name: &str| element.attrs.iter()
+                .find(|a| tree.resolve(a.name.local).eq_ignore_ascii_case(name))
+                .map(|a| a.value.trim().to_ascii_lowercase());
+            if spellcheck.is_none() {
+                spellcheck = match attr("spellcheck").as_deref() {
+                    Some("" | "true") => Some(true),
+                    Some("false") => Some(false),
+                    _ => None,
+                };
+            }
+            if editable.is_none() {
+                let tag = tree.resolve(element.name.local).to_ascii_lowercase();
+                editable = match tag.as_str() {
+                    "textarea" => Some(true),
+                    "input" => Some(matches!(attr("type").as_deref(), None | Some("text" | "search"))),
+                    _ => attr("contenteditable").map(|v| v != "false"),
+                };
+            }
+        }
+        id = current.parent;
+    }
+    editable == Some(true) && spellcheck != Some(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const AFF: &str = "SET UTF-8\nTRY esianrtolcdugmphbyfvkwz\nREP 1\nREP f ph\n\
+        PFX U Y 1\nPFX U 0 un .\n\
+        SFX S Y 2\nSFX S y ies [^aeiou]y\nSFX S 0 s [^y]\n";
+    const DIC: &str = "4\nhappy/U\nword/S\nberry/S\nParis\n";
+    
+    fn checker() -> SpellChecker {
+        SpellChecker::new().with_dictionary(Dictionary::from_hunspell("en_US", AFF, DIC))
+    }
+    
+    #[test]
+    fn test_hunspell_dictionary() {
+        let checker = checker();
+        for word in ["happy", "unhappy", "words", "berries", "Word", "WORDS", "Paris", "PARIS"] {
+            assert!(checker.check_word(word), "{}", word);
+        }
+        for word in ["berrys", "paris", "wOrd", "unword"] {
+            assert!(!checker.check_word(word), "{}", word);
+        }
+        
+        let text = "Happy wrods, 2nd berrys at www.example.com and unhappy’s";
+        let misspelled: Vec<_> = checker.check(text).into_iter().map(|m| m.word).collect();
+        assert_eq!(misspelled, ["wrods", "berrys", "at", "and", "unhappy’s"]);
+    }
+    
+    #[test]
+    fn test_suggestions() {
+        let checker = checker();
+        assert_eq!(checker.suggest("wrod", 3), ["word"]);
+        assert_eq!(checker.suggest("Happyword", 3), ["Happy word"]);
+        
+        let mut text = "a wrod here".to_string();
+        let menu = checker.context_menu(&text, 4).unwrap();
+        assert_eq!(menu.misspelling.range, 2..6);
+        assert_eq!(menu.suggestions, ["word"]);
+        checker.apply(&mut text, &menu, SpellcheckAction::Replace("word".into()));
+        assert_eq!(text, "a word here");
+        
+        let menu = checker.context_menu("fOS", 0).unwrap();
+        checker.clone().apply(&mut String::new(), &menu, SpellcheckAction::AddToDictionary);
+        assert!(checker.check("fOS").is_empty());
+    }
+    
+    #[test]
+    fn test_spellcheck_attribute() {
+        let html = r#"<html><body><p>plain</p><div contenteditable>edit<span spellcheck="false">off</span></div>
+            <textarea>area</textarea><p spellcheck="true">not editable</p></body></html>"#;
+        let document = fos_html::parse(html);
+        let tree = document.tree();
+        let text_of = |content: &str| {
+            (0..tree.len() as u32).map(NodeId).find(|&id| {
+                tree.get(id).and_then(|n| n.as_text()).is_some_and(|t| t.trim() == content)
+            }).unwrap()
+        };
+        assert!(!spellcheck_enabled(tree, text_of("plain")));
+        assert!(spellcheck_enabled(tree, text_of("edit")));
+        assert!(!spellcheck_enabled(tree, text_of("off")));
+        assert!(spellcheck_enabled(tree, text_of("area")));
+        assert!(!spellcheck_enabled(tree, text_of("not editable")));
+    }
+}
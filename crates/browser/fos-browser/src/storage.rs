@@ -123,6 +123,13 @@ impl StorageManager {
         }
     }
     
+    /// Keys of localStorage for origin, sorted
+    pub fn local_keys(&mut self, origin: &str) -> Vec<String> {
+        let mut keys: Vec<String> = self.local_storage_for(origin).keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+    
     /// Get localStorage length
     pub fn local_length(&mut self, origin: &str) -> usize {
         self.local_storage_for(origin).len()
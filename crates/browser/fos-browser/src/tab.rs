@@ -1,14 +1,48 @@
 //! Tab Management
 //!
-//! Handles multiple browser tabs.
+//! Handles multiple browser tabs. Under memory pressure background tabs
+//! can be hibernated: they drop their page and cached documents but keep
+//! their history, and reload when shown again.
+//!
+//! Each window has its own tab manager. Managers created with the same
+//! [`TabIds`] never hand out the same ID, so tabs can be dragged from one
+//! window to another and keep their identity.
+//!
+//! A tab in reader mode shows the article extracted from its page, and
+//! keeps the page to go back to.
+//!
+//! Back and forward traverse the joint session history of the tab's
+//! [`FrameTree`], so they undo iframe navigations as well as page loads.
 
+use crate::frame_tree::{FrameId, FrameTree};
+use crate::memory::{HibernationCandidate, TabMemory};
 use crate::navigation::History;
+use crate::navigation_api::{BackForwardCache, CachedPage};
 use crate::page::Page;
+use crate::reader;
+use crate::tiered::TieredStats;
+use crate::session::{PendingRestore, SessionState, TabState};
+use fos_a11y::{ReadingModeSettings, ZoomLevel};
+use fos_engine::MemoryStats;
+#[cfg(feature = "extensions")]
+use crate::extension_api::{ExtensionTabs, TabInfo};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Instant;
 
 /// Tab ID type
 pub type TabId = u32;
 
+/// What going back or forward changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Traversal {
+    /// The tab shows the page at this URL
+    Page(String),
+    /// Only frames of the shown page changed document
+    Frames,
+}
+
 /// A browser tab
 #[derive(Debug)]
 pub struct Tab {
@@ -24,12 +58,33 @@ pub struct Tab {
     pub loading: bool,
     /// Favicon (if loaded)
     pub favicon: Option<Vec<u8>>,
-    /// Navigation history
+    /// Navigation history of the top-level page, as sessions save it
     pub history: History,
+    /// Frames of the page and the joint session history they share
+    pub frame_tree: FrameTree,
     /// Cached HTML content (for instant tab switching)
     pub cached_html: Option<String>,
+    /// Pages left by navigation, restored on back/forward (keyed by URL)
+    pub bfcache: BackForwardCache,
     /// Needs reload from network
     pub needs_network_load: bool,
+    /// Tab is playing sound (tab strip indicator)
+    pub audible: bool,
+    /// Tab audio is muted by the user
+    pub audio_muted: bool,
+    /// Tab is being captured or is capturing the screen (tab strip indicator)
+    pub capturing: bool,
+    /// Scroll offset and form values from a restored session, applied when
+    /// the page loads
+    pub pending_restore: Option<PendingRestore>,
+    /// Page was dropped to save memory; it reloads when the tab is shown
+    pub hibernated: bool,
+    /// When the tab was last shown
+    pub last_active: Instant,
+    /// Text-only zoom, kept across navigations in this tab
+    pub text_zoom: ZoomLevel,
+    /// HTML of the page while the tab shows its reading view
+    reader_source: Option<String>,
 }
 
 impl Tab {
@@ -48,57 +103,157 @@ impl Tab {
             loading: false,
             favicon: None,
             history,
+            frame_tree: FrameTree::new(url),
             cached_html: None,
+            bfcache: BackForwardCache::new(),
             needs_network_load: needs_load,
+            audible: false,
+            audio_muted: false,
+            capturing: false,
+            pending_restore: None,
+            hibernated: false,
+            last_active: Instant::now(),
+            text_zoom: ZoomLevel::default(),
+            reader_source: None,
         }
     }
     
     /// Navigate to URL (triggers network load)
     pub fn navigate(&mut self, url: &str) {
+        self.cache_current_page();
         self.history.navigate(url);
+        self.frame_tree.navigate(self.frame_tree.root(), url, None, None, false);
         self.url = url.to_string();
         self.loading = true;
         self.title = "Loading...".to_string();
         self.cached_html = None; // Clear cache for new URL
+        self.reader_source = None;
         self.needs_network_load = true;
+        self.hibernated = false;
     }
     
-    /// Go back in history
-    pub fn go_back(&mut self) -> Option<String> {
-        if let Some(url) = self.history.go_back() {
-            self.url = url.clone();
-            self.loading = true;
-            self.title = "Loading...".to_string();
-            self.cached_html = None; // TODO: Could cache per-URL
-            self.needs_network_load = true;
-            Some(url)
-        } else {
-            None
+    /// Show the article of the loaded page in a reading view styled by
+    /// `settings`; false if the page has no article
+    pub fn enter_reader_mode(&mut self, settings: &ReadingModeSettings) -> bool {
+        if self.loading {
+            return false;
+        }
+        let source = match self.reader_source.take() {
+            Some(source) => source,
+            None => match self.cached_html.take() {
+                Some(html) => html,
+                None => return false,
+            },
+        };
+        match reader::extract(&source, &self.url) {
+            Some(article) => {
+                self.cached_html = Some(article.to_html(settings));
+                self.reader_source = Some(source);
+                self.needs_network_load = false;
+                true
+            }
+            None => {
+                self.cached_html = Some(source);
+                false
+            }
+        }
+    }
+    
+    /// Go back from the reading view to the page; false if not in it
+    pub fn exit_reader_mode(&mut self) -> bool {
+        match self.reader_source.take() {
+            Some(source) => {
+                self.cached_html = Some(source);
+                true
+            }
+            None => false,
         }
     }
     
-    /// Go forward in history
-    pub fn go_forward(&mut self) -> Option<String> {
-        if let Some(url) = self.history.go_forward() {
-            self.url = url.clone();
-            self.loading = true;
-            self.title = "Loading...".to_string();
-            self.cached_html = None;
-            self.needs_network_load = true;
-            Some(url)
+    /// Whether the tab shows its reading view
+    pub fn is_reader_mode(&self) -> bool {
+        self.reader_source.is_some()
+    }
+    
+    /// Go back in the joint session history
+    pub fn go_back(&mut self) -> Option<Traversal> {
+        self.traverse(-1)
+    }
+    
+    /// Go forward in the joint session history
+    pub fn go_forward(&mut self) -> Option<Traversal> {
+        self.traverse(1)
+    }
+    
+    /// Move `delta` steps through the joint session history
+    fn traverse(&mut self, delta: i32) -> Option<Traversal> {
+        let root = self.frame_tree.root();
+        let changed = self.frame_tree.go(delta);
+        if changed.is_empty() {
+            return None;
+        }
+        let Some((_, url)) = changed.into_iter().find(|(frame, _)| *frame == root) else {
+            return Some(Traversal::Frames);
+        };
+        self.cache_current_page();
+        if delta < 0 {
+            self.history.go_back();
         } else {
-            None
+            self.history.go_forward();
+        }
+        self.show_history_entry(&url);
+        Some(Traversal::Page(url))
+    }
+    
+    /// Navigate a child frame of the page, such as the target of a link;
+    /// going back returns the frame to its previous document
+    pub fn navigate_frame(&mut self, frame: FrameId, url: &str, initiator: FrameId) -> bool {
+        if !self.frame_tree.navigate(frame, url, None, Some(initiator), false) {
+            return false;
+        }
+        // The new step drops every later one, the page's own included
+        self.history.clear_forward();
+        true
+    }
+    
+    /// Keep the loaded page for back/forward
+    fn cache_current_page(&mut self) {
+        self.exit_reader_mode();
+        if let Some(html) = self.cached_html.take() {
+            if !self.loading {
+                let page = CachedPage::new(&self.url, &self.title, html);
+                self.bfcache.store(&self.url, page, &[]);
+            }
+        }
+    }
+    
+    /// Switch to a history entry, from the back/forward cache when possible
+    fn show_history_entry(&mut self, url: &str) {
+        self.url = url.to_string();
+        match self.bfcache.take(url) {
+            Some(page) => {
+                self.title = page.title;
+                self.loading = false;
+                self.cached_html = Some(page.html);
+                self.needs_network_load = false;
+            }
+            None => {
+                self.loading = true;
+                self.title = "Loading...".to_string();
+                self.cached_html = None;
+                self.needs_network_load = true;
+            }
         }
     }
     
     /// Can go back
     pub fn can_go_back(&self) -> bool {
-        self.history.can_go_back()
+        self.frame_tree.can_go_back()
     }
     
     /// Can go forward
     pub fn can_go_forward(&self) -> bool {
-        self.history.can_go_forward()
+        self.frame_tree.can_go_forward()
     }
     
     /// Set page content after loading
@@ -108,6 +263,83 @@ impl Tab {
         self.loading = false;
     }
     
+    /// Approximate bytes held by the page and cached documents
+    pub fn memory_usage(&self) -> usize {
+        let page = self.page.as_ref().map_or(0, |p| {
+            p.html.len() + p.rendered.as_ref().map_or(0, |r| r.pixels.len() * 4)
+        });
+        page + self.cached_html.as_ref().map_or(0, String::len) + self.bfcache.memory_size()
+    }
+    
+    /// Memory by tier; the window adds the page it shows for the active tab
+    pub fn memory_report(&self) -> TabMemory {
+        let mut tiers = TieredStats::default();
+        let mut dom_nodes = 0;
+        if let Some(page) = &self.page {
+            tiers.hot_count = 1;
+            tiers.hot_bytes = page.html.len() + page.rendered.as_ref().map_or(0, |r| r.pixels.len() * 4);
+            dom_nodes = page.document().map_or(0, |doc| doc.lock().unwrap().tree().len());
+        }
+        if let Some(html) = &self.cached_html {
+            tiers.warm_count = 1;
+            tiers.warm_bytes = html.len();
+        }
+        tiers.cold_count = self.bfcache.len();
+        tiers.total_bytes = self.memory_usage();
+        
+        TabMemory {
+            tab_id: self.id,
+            title: self.title.clone(),
+            url: self.url.clone(),
+            hibernated: self.hibernated,
+            stats: MemoryStats {
+                heap_used: tiers.total_bytes,
+                dom_nodes,
+                ..Default::default()
+            },
+            tiers,
+        }
+    }
+    
+    /// Drop the page, its cached HTML and the back/forward cache, keeping
+    /// URL, title, history and any pending restore; returns the bytes freed
+    pub fn hibernate(&mut self) -> usize {
+        let freed = self.memory_usage();
+        self.page = None;
+        self.cached_html = None;
+        self.reader_source = None;
+        self.bfcache.clear();
+        self.loading = false;
+        self.needs_network_load = true;
+        self.hibernated = true;
+        freed
+    }
+    
+    /// Drop the back/forward cache, keeping the page; returns the bytes
+    /// freed
+    pub fn purge_caches(&mut self) -> usize {
+        let freed = self.bfcache.memory_size();
+        self.bfcache.clear();
+        freed
+    }
+    
+    /// Save for session restore
+    ///
+    /// Scroll offset and form values come from a pending restore; the
+    /// browser fills them in for the page it is showing.
+    pub fn session_state(&self) -> TabState {
+        let pending = self.pending_restore.clone().unwrap_or_default();
+        TabState {
+            id: self.id,
+            url: self.url.clone(),
+            title: self.title.clone(),
+            back: self.history.back_entries(),
+            forward: self.history.forward_entries(),
+            scroll: pending.scroll,
+            form_fields: pending.form_fields,
+        }
+    }
+    
     /// Get display title (truncated)
     pub fn display_title(&self, max_chars: usize) -> String {
         if self.title.len() > max_chars {
@@ -118,6 +350,27 @@ impl Tab {
     }
 }
 
+/// Source of tab IDs shared by the tab managers of all windows
+#[derive(Debug, Clone)]
+pub struct TabIds(Arc<AtomicU32>);
+
+impl TabIds {
+    pub fn new() -> Self {
+        Self(Arc::new(AtomicU32::new(1)))
+    }
+    
+    /// Take the next unused ID
+    pub fn next(&self) -> TabId {
+        self.0.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl Default for TabIds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Tab manager
 #[derive(Debug)]
 pub struct TabManager {
@@ -127,25 +380,30 @@ pub struct TabManager {
     order: Vec<TabId>,
     /// Active tab ID
     active: Option<TabId>,
-    /// Next tab ID
-    next_id: TabId,
+    /// Source of new tab IDs
+    ids: TabIds,
 }
 
 impl TabManager {
     /// Create a new tab manager
     pub fn new() -> Self {
+        Self::with_ids(TabIds::new())
+    }
+    
+    /// Create a tab manager whose tab IDs are unique among all managers
+    /// sharing `ids`
+    pub fn with_ids(ids: TabIds) -> Self {
         Self {
             tabs: HashMap::new(),
             order: Vec::new(),
             active: None,
-            next_id: 1,
+            ids,
         }
     }
     
     /// Create a new tab
     pub fn new_tab(&mut self, url: &str) -> TabId {
-        let id = self.next_id;
-        self.next_id += 1;
+        let id = self.ids.next();
         
         let mut tab = Tab::new(id, url);
         if url != "about:blank" {
@@ -154,7 +412,7 @@ impl TabManager {
         
         self.tabs.insert(id, tab);
         self.order.push(id);
-        self.active = Some(id);
+        self.activate(id);
         
         id
     }
@@ -166,7 +424,10 @@ impl TabManager {
         
         // Update active tab
         if self.active == Some(id) {
-            self.active = self.order.last().copied();
+            self.active = None;
+            if let Some(&last) = self.order.last() {
+                self.activate(last);
+            }
         }
     }
     
@@ -180,8 +441,19 @@ impl TabManager {
     /// Set active tab
     pub fn set_active(&mut self, id: TabId) {
         if self.tabs.contains_key(&id) {
-            self.active = Some(id);
+            self.activate(id);
+        }
+    }
+    
+    /// Show a tab, noting when the one it replaces was last seen
+    fn activate(&mut self, id: TabId) {
+        let now = Instant::now();
+        for shown in [self.active, Some(id)].into_iter().flatten() {
+            if let Some(tab) = self.tabs.get_mut(&shown) {
+                tab.last_active = now;
+            }
         }
+        self.active = Some(id);
     }
     
     /// Get active tab
@@ -194,6 +466,16 @@ impl TabManager {
         self.active.and_then(|id| self.tabs.get_mut(&id))
     }
     
+    /// Get tab by ID
+    pub fn get(&self, id: TabId) -> Option<&Tab> {
+        self.tabs.get(&id)
+    }
+    
+    /// Get mutable tab by ID
+    pub fn get_mut(&mut self, id: TabId) -> Option<&mut Tab> {
+        self.tabs.get_mut(&id)
+    }
+    
     /// Get all tabs in order
     pub fn tabs_in_order(&self) -> Vec<&Tab> {
         self.order.iter()
@@ -226,7 +508,7 @@ impl TabManager {
         if let Some(active_id) = self.active {
             if let Some(pos) = self.order.iter().position(|&id| id == active_id) {
                 if pos > 0 {
-                    self.active = Some(self.order[pos - 1]);
+                    self.activate(self.order[pos - 1]);
                 }
             }
         }
@@ -237,16 +519,130 @@ impl TabManager {
         if let Some(active_id) = self.active {
             if let Some(pos) = self.order.iter().position(|&id| id == active_id) {
                 if pos + 1 < self.order.len() {
-                    self.active = Some(self.order[pos + 1]);
+                    self.activate(self.order[pos + 1]);
                 }
             }
         }
     }
     
+    /// Move a tab to position `index` of the strip
+    pub fn move_tab(&mut self, id: TabId, index: usize) -> bool {
+        let Some(pos) = self.order.iter().position(|&t| t == id) else {
+            return false;
+        };
+        self.order.remove(pos);
+        self.order.insert(index.min(self.order.len()), id);
+        true
+    }
+    
+    /// Take a tab out of the strip, e.g. when it is dragged to another
+    /// window; the next tab in line becomes active in its place
+    pub fn detach_tab(&mut self, id: TabId) -> Option<Tab> {
+        let pos = self.order.iter().position(|&t| t == id)?;
+        self.order.remove(pos);
+        let tab = self.tabs.remove(&id)?;
+        if self.active == Some(id) {
+            self.active = None;
+            if let Some(&next) = self.order.get(pos).or(self.order.last()) {
+                self.activate(next);
+            }
+        }
+        Some(tab)
+    }
+    
+    /// Put a tab detached from another window at position `index`
+    pub fn attach_tab(&mut self, tab: Tab, index: usize, activate: bool) {
+        let id = tab.id;
+        self.tabs.insert(id, tab);
+        self.order.insert(index.min(self.order.len()), id);
+        if activate || self.active.is_none() {
+            self.activate(id);
+        }
+    }
+    
     /// Check if a tab is active
     pub fn is_active(&self, id: TabId) -> bool {
         self.active == Some(id)
     }
+    
+    /// Background tabs that may be hibernated: not already hibernated,
+    /// holding memory, and not playing sound or capturing
+    pub fn hibernation_candidates(&self) -> Vec<HibernationCandidate> {
+        self.tabs.values()
+            .filter(|t| !self.is_active(t.id) && !t.hibernated && !t.audible && !t.capturing)
+            .map(|t| HibernationCandidate { tab_id: t.id, bytes: t.memory_usage(), last_active: t.last_active })
+            .filter(|c| c.bytes > 0)
+            .collect()
+    }
+    
+    /// Save open tabs for session restore
+    pub fn session_state(&self) -> SessionState {
+        SessionState {
+            tabs: self.tabs_in_order().into_iter().map(Tab::session_state).collect(),
+            active: self.active,
+        }
+    }
+    
+    /// Reopen the tabs of a saved session, after any already open
+    ///
+    /// Restored tabs get new IDs and load when first shown.
+    pub fn restore_session(&mut self, session: &SessionState) {
+        for saved in &session.tabs {
+            let id = self.ids.next();
+            
+            let mut tab = Tab::new(id, &saved.url);
+            tab.title = saved.title.clone();
+            tab.history = History::from_entries(&saved.back, &saved.url, &saved.forward);
+            tab.frame_tree = FrameTree::from_entries(&saved.back, &saved.url, &saved.forward);
+            tab.pending_restore = Some(PendingRestore {
+                scroll: saved.scroll,
+                form_fields: saved.form_fields.clone(),
+            });
+            
+            self.tabs.insert(id, tab);
+            self.order.push(id);
+            if session.active == Some(saved.id) || self.active.is_none() {
+                self.active = Some(id);
+            }
+        }
+    }
+}
+
+/// Tabs for `chrome.tabs`
+#[cfg(feature = "extensions")]
+impl ExtensionTabs for TabManager {
+    fn tabs(&self) -> Vec<TabInfo> {
+        self.tabs_in_order().into_iter()
+            .map(|tab| TabInfo {
+                id: tab.id,
+                url: tab.url.clone(),
+                title: tab.title.clone(),
+                active: self.is_active(tab.id),
+            })
+            .collect()
+    }
+    
+    fn create(&mut self, url: &str, active: bool) -> TabInfo {
+        let previous = self.active;
+        let id = self.new_tab(url);
+        if !active && previous.is_some() {
+            self.active = previous;
+        }
+        let tab = &self.tabs[&id];
+        TabInfo { id, url: tab.url.clone(), title: tab.title.clone(), active: self.is_active(id) }
+    }
+    
+    fn update(&mut self, id: TabId, url: Option<&str>, active: bool) -> Option<TabInfo> {
+        let tab = self.tabs.get_mut(&id)?;
+        if let Some(url) = url {
+            tab.navigate(url);
+        }
+        if active {
+            self.set_active(id);
+        }
+        let tab = &self.tabs[&id];
+        Some(TabInfo { id, url: tab.url.clone(), title: tab.title.clone(), active: self.is_active(id) })
+    }
 }
 
 impl Default for TabManager {
@@ -254,3 +650,75 @@ impl Default for TabManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_tab_drag_between_windows() {
+        let ids = TabIds::new();
+        let mut first = TabManager::with_ids(ids.clone());
+        let mut second = TabManager::with_ids(ids);
+        let a = first.new_tab("about:blank");
+        let b = first.new_tab("about:blank");
+        let c = second.new_tab("about:blank");
+        assert!(c != a && c != b);
+        
+        // Dragging the active tab out activates its neighbour
+        let tab = first.detach_tab(b).unwrap();
+        assert!(first.is_active(a));
+        second.attach_tab(tab, 0, true);
+        assert!(second.is_active(b));
+        let order = |tabs: &TabManager| tabs.tabs_in_order().iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(order(&second), vec![b, c]);
+        
+        assert!(second.move_tab(b, 5));
+        assert_eq!(order(&second), vec![c, b]);
+        assert!(first.detach_tab(b).is_none());
+    }
+    
+    #[test]
+    fn test_back_forward_through_frames() {
+        let mut tab = Tab::new(1, "about:blank");
+        tab.navigate("https://a.com/");
+        tab.navigate("https://a.com/frames");
+        let root = tab.frame_tree.root();
+        let left = tab.frame_tree.create_frame(root, "left").unwrap();
+        tab.frame_tree.navigate(left, "https://a.com/menu", None, Some(root), false);
+        
+        // A link targeting the frame adds an entry that back undoes first
+        assert!(tab.navigate_frame(left, "https://a.com/page2", root));
+        assert_eq!(tab.go_back(), Some(Traversal::Frames));
+        assert_eq!(tab.frame_tree.current_url(left), Some("https://a.com/menu"));
+        assert_eq!(tab.go_back(), Some(Traversal::Page("https://a.com/".to_string())));
+        assert_eq!(tab.url, "https://a.com/");
+        assert!(!tab.can_go_back());
+        assert_eq!(tab.go_back(), None);
+        
+        assert_eq!(tab.go_forward(), Some(Traversal::Page("https://a.com/frames".to_string())));
+        assert_eq!(tab.history.current(), Some("https://a.com/frames"));
+        assert_eq!(tab.go_forward(), Some(Traversal::Frames));
+        assert!(!tab.can_go_forward());
+    }
+    
+    #[test]
+    fn test_reader_mode() {
+        let paragraph = "<p>Reader mode keeps the article text, drops the navigation around it, and shows it in a calm layout.</p>";
+        let page = format!("<html><body><nav><a href=\"/\">Home</a></nav><article>{}</article></body></html>", paragraph.repeat(3));
+        let mut tab = Tab::new(1, "https://example.com/post");
+        let settings = ReadingModeSettings::sepia();
+        assert!(!tab.enter_reader_mode(&settings));
+        
+        tab.cached_html = Some(page.clone());
+        tab.needs_network_load = false;
+        assert!(tab.enter_reader_mode(&settings));
+        assert!(tab.is_reader_mode());
+        let view = tab.cached_html.clone().unwrap();
+        assert!(view.contains("#f5e6c8") && !view.contains("Home"));
+        
+        assert!(tab.exit_reader_mode());
+        assert_eq!(tab.cached_html.as_deref(), Some(page.as_str()));
+        assert!(!tab.exit_reader_mode());
+    }
+}
@@ -0,0 +1,84 @@
+//! Telemetry
+//!
+//! Browser events recorded for diagnostics. Events say what happened, not
+//! where: they carry no URLs or hosts, and stay on the device.
+
+use std::collections::{HashMap, VecDeque};
+
+/// Events kept before the oldest are dropped
+const EVENT_LIMIT: usize = 1000;
+
+/// A recorded event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TelemetryEvent {
+    /// A certificate error page was shown
+    CertificateErrorShown {
+        code: &'static str,
+        /// The page offered to proceed anyway
+        overridable: bool,
+    },
+    /// The user proceeded despite a certificate error
+    CertificateErrorOverridden {
+        code: &'static str,
+    },
+}
+
+impl TelemetryEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CertificateErrorShown { .. } => "certificate_error_shown",
+            Self::CertificateErrorOverridden { .. } => "certificate_error_overridden",
+        }
+    }
+}
+
+/// Recent events and a count of each kind
+#[derive(Debug, Default)]
+pub struct Telemetry {
+    events: VecDeque<TelemetryEvent>,
+    counts: HashMap<&'static str, u64>,
+}
+
+impl Telemetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Record an event
+    pub fn record(&mut self, event: TelemetryEvent) {
+        log::debug!("Telemetry: {:?}", event);
+        *self.counts.entry(event.name()).or_default() += 1;
+        if self.events.len() == EVENT_LIMIT {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+    
+    /// Recent events, oldest first
+    pub fn events(&self) -> impl Iterator<Item = &TelemetryEvent> {
+        self.events.iter()
+    }
+    
+    /// Times an event of a kind was recorded, by [`TelemetryEvent::name`]
+    pub fn count(&self, name: &str) -> u64 {
+        self.counts.get(name).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_record_events() {
+        let mut telemetry = Telemetry::new();
+        for _ in 0..EVENT_LIMIT + 1 {
+            telemetry.record(TelemetryEvent::CertificateErrorShown { code: "ERR_CERT_DATE_INVALID", overridable: true });
+        }
+        telemetry.record(TelemetryEvent::CertificateErrorOverridden { code: "ERR_CERT_DATE_INVALID" });
+        
+        assert_eq!(telemetry.count("certificate_error_shown"), EVENT_LIMIT as u64 + 1);
+        assert_eq!(telemetry.count("certificate_error_overridden"), 1);
+        assert_eq!(telemetry.events().count(), EVENT_LIMIT);
+    }
+}
@@ -3,13 +3,55 @@
 //! Main browser chrome combining all UI components.
 
 use winit::event::MouseButton;
-use crate::tab::TabManager;
-use super::tab_bar::{TabBar, TabBarAction, TAB_BAR_WIDTH};
+use fos_a11y::{
+    ChromeAccessibility, ChromeMenuItem, ChromeTab, ChromeToolbarItem, ChromeUiAction,
+    KeyboardShortcut, NodeBounds, ToolbarControl,
+};
+use crate::ime::Composition;
+use crate::tab::{TabId, TabManager};
+use super::tab_bar::{TabBar, TabBarAction, TAB_BAR_WIDTH, TAB_HEIGHT};
 use super::url_bar::{UrlBar, UrlBarAction, URL_BAR_HEIGHT};
 
 /// Content area background color
 const CONTENT_BG: u32 = 0xFF0D0D0D;
 
+/// Keyboard focus ring color
+const FOCUS_RING: u32 = 0xFF40C0C0;
+
+/// Application menu colors and metrics
+const MENU_BG: u32 = 0xFF254A4A;
+const MENU_TEXT: u32 = 0xFFE0E0E0;
+const MENU_WIDTH: u32 = 180;
+const MENU_ITEM_HEIGHT: u32 = 20;
+
+/// Application menu entries (id, label, shortcut)
+const MENU_ITEMS: [(&str, &str, &str); 5] = [
+    ("new-tab", "New tab", "T"),
+    ("close-tab", "Close tab", "W"),
+    ("reload", "Reload", "R"),
+    ("install-app", "Install app", ""),
+    ("devtools", "Developer tools", "F12"),
+];
+
+/// Command for the app resulting from keyboard use of the chrome
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChromeCommand {
+    /// Chrome state changed, redraw only
+    Redraw,
+    /// Active tab changed or a tab was opened/closed
+    TabsChanged,
+    /// Reload the active page
+    Reload,
+    /// Toggle developer tools
+    ToggleDevTools,
+    /// Install the active page's web app
+    InstallApp,
+    /// Toggle audio mute of a tab
+    ToggleTabMute(TabId),
+    /// Keyboard focus returned to page content
+    FocusContent,
+}
+
 /// Browser chrome
 #[derive(Debug)]
 pub struct Chrome {
@@ -23,6 +65,8 @@ pub struct Chrome {
     /// Window dimensions
     width: u32,
     height: u32,
+    /// Accessibility tree and keyboard model for the chrome
+    pub a11y: ChromeAccessibility,
 }
 
 impl Chrome {
@@ -34,9 +78,16 @@ impl Chrome {
             mouse_y: 0,
             width: 1024,
             height: 768,
+            a11y: Self::create_a11y(),
         }
     }
     
+    fn create_a11y() -> ChromeAccessibility {
+        let mut a11y = ChromeAccessibility::new();
+        a11y.attach_bridge(fos_a11y::create_platform_bridge());
+        a11y
+    }
+    
     /// Render the entire chrome
     pub fn render(
         &mut self,
@@ -82,6 +133,167 @@ impl Chrome {
             content_y_end,
             tab_bar_width,
         );
+        
+        // Keep the chrome accessibility tree in sync with what is drawn
+        self.sync_accessibility(tabs);
+        self.render_menu(buffer, buffer_width, buffer_height);
+        self.render_focus_ring(buffer, buffer_width, buffer_height);
+    }
+    
+    /// Push tab strip and toolbar state into the chrome accessibility tree
+    fn sync_accessibility(&mut self, tabs: &TabManager) {
+        let mut y = 4.0;
+        let chrome_tabs = tabs.tabs_in_order().into_iter().map(|tab| {
+            let entry = ChromeTab {
+                tab_id: tab.id as u64,
+                title: tab.title.clone(),
+                active: tabs.is_active(tab.id),
+                audible: tab.audible,
+                muted: tab.audio_muted,
+                capturing: tab.capturing,
+                bounds: NodeBounds { x: 2.0, y, width: (TAB_BAR_WIDTH - 4) as f64, height: TAB_HEIGHT as f64 },
+            };
+            y += TAB_HEIGHT as f64 + 1.0;
+            entry
+        }).collect();
+        self.a11y.set_tabs(chrome_tabs);
+        
+        let bar_y = self.height.saturating_sub(URL_BAR_HEIGHT) as f64;
+        let bar_x = TAB_BAR_WIDTH as f64;
+        let bar_width = self.width.saturating_sub(TAB_BAR_WIDTH) as f64;
+        let menu_width = URL_BAR_HEIGHT as f64;
+        self.a11y.set_toolbar(vec![
+            ChromeToolbarItem {
+                control: ToolbarControl::UrlBar,
+                enabled: true,
+                value: Some(self.url_bar.input.clone()),
+                bounds: NodeBounds { x: bar_x, y: bar_y, width: bar_width - menu_width, height: URL_BAR_HEIGHT as f64 },
+            },
+            ChromeToolbarItem {
+                control: ToolbarControl::Menu,
+                enabled: true,
+                value: None,
+                bounds: NodeBounds { x: bar_x + bar_width - menu_width, y: bar_y, width: menu_width, height: URL_BAR_HEIGHT as f64 },
+            },
+        ]);
+        
+        let menu = MENU_ITEMS.iter().enumerate().map(|(i, (id, label, key))| {
+            let item = match *key {
+                "" => ChromeMenuItem::new(id, label),
+                key if key.starts_with('F') => ChromeMenuItem::new(id, label).with_shortcut(KeyboardShortcut::new(key)),
+                key => ChromeMenuItem::new(id, label).with_shortcut(KeyboardShortcut::new(key).ctrl()),
+            };
+            ChromeMenuItem {
+                bounds: self.menu_item_bounds(i),
+                ..item
+            }
+        }).collect();
+        self.a11y.set_menu(menu);
+    }
+    
+    /// Menu item rows, stacked upward from the URL bar at the right edge
+    fn menu_item_bounds(&self, index: usize) -> NodeBounds {
+        let count = MENU_ITEMS.len() as u32;
+        let x = self.width.saturating_sub(MENU_WIDTH);
+        let top = self.height.saturating_sub(URL_BAR_HEIGHT + MENU_ITEM_HEIGHT * count);
+        NodeBounds {
+            x: x as f64,
+            y: (top + index as u32 * MENU_ITEM_HEIGHT) as f64,
+            width: MENU_WIDTH as f64,
+            height: MENU_ITEM_HEIGHT as f64,
+        }
+    }
+    
+    /// Render the application menu popup when open
+    fn render_menu(&self, buffer: &mut [u32], buffer_width: usize, buffer_height: usize) {
+        if !self.a11y.is_menu_open() {
+            return;
+        }
+        
+        for (i, (_, label, _)) in MENU_ITEMS.iter().enumerate() {
+            let bounds = self.menu_item_bounds(i);
+            let (x, y) = (bounds.x as usize, bounds.y as usize);
+            for dy in 0..MENU_ITEM_HEIGHT as usize {
+                for dx in 0..MENU_WIDTH as usize {
+                    let (px, py) = (x + dx, y + dy);
+                    if px < buffer_width && py < buffer_height {
+                        buffer[py * buffer_width + px] = MENU_BG;
+                    }
+                }
+            }
+            let mut char_x = x as i32 + 8;
+            for c in label.chars() {
+                self.draw_char(buffer, buffer_width, buffer_height, char_x, y as i32 + 6, c, MENU_TEXT);
+                char_x += 7;
+            }
+        }
+    }
+    
+    /// Draw the keyboard focus ring around the focused chrome item
+    fn render_focus_ring(&self, buffer: &mut [u32], buffer_width: usize, buffer_height: usize) {
+        let Some((bounds, indicator)) = self.a11y.focus_ring() else {
+            return;
+        };
+        
+        let width = indicator.width.max(1.0) as usize;
+        let x0 = bounds.x.max(0.0) as usize;
+        let y0 = bounds.y.max(0.0) as usize;
+        let x1 = (bounds.x + bounds.width).max(0.0) as usize;
+        let y1 = (bounds.y + bounds.height).max(0.0) as usize;
+        
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let on_edge = px < x0 + width || px + width >= x1 || py < y0 + width || py + width >= y1;
+                if on_edge && px < buffer_width && py < buffer_height {
+                    buffer[py * buffer_width + px] = FOCUS_RING;
+                }
+            }
+        }
+    }
+    
+    /// Route a key press through the chrome accessibility model.
+    /// Returns `None` if the key should be handled by the page.
+    pub fn handle_a11y_key(&mut self, key: &KeyboardShortcut, tabs: &mut TabManager) -> Option<ChromeCommand> {
+        let action = self.a11y.handle_key(key)?;
+        
+        let command = match action {
+            ChromeUiAction::FocusMoved(_) | ChromeUiAction::MenuToggled(_) => ChromeCommand::Redraw,
+            ChromeUiAction::SelectTab(id) => {
+                tabs.set_active(id as u32);
+                ChromeCommand::TabsChanged
+            }
+            ChromeUiAction::CloseTab(id) => {
+                tabs.close_tab(id as u32);
+                ChromeCommand::TabsChanged
+            }
+            ChromeUiAction::ToggleTabMute(id) => ChromeCommand::ToggleTabMute(id as TabId),
+            ChromeUiAction::EditUrlBar => {
+                self.url_bar.focus();
+                ChromeCommand::Redraw
+            }
+            ChromeUiAction::PressToolbar(ToolbarControl::Reload) => ChromeCommand::Reload,
+            ChromeUiAction::PressToolbar(_) => ChromeCommand::Redraw,
+            ChromeUiAction::ActivateMenuItem(id) => match id.as_str() {
+                "new-tab" => {
+                    tabs.new_tab("about:blank");
+                    ChromeCommand::TabsChanged
+                }
+                "close-tab" => {
+                    tabs.close_active_tab();
+                    ChromeCommand::TabsChanged
+                }
+                "reload" => ChromeCommand::Reload,
+                "devtools" => ChromeCommand::ToggleDevTools,
+                "install-app" => ChromeCommand::InstallApp,
+                _ => ChromeCommand::Redraw,
+            },
+            ChromeUiAction::FocusContent => {
+                self.url_bar.unfocus();
+                ChromeCommand::FocusContent
+            }
+        };
+        
+        Some(command)
     }
     
     /// Render content area placeholder (only when loading)
@@ -164,6 +376,7 @@ impl Chrome {
     pub fn handle_mouse_move(&mut self, x: i32, y: i32) {
         self.mouse_x = x;
         self.mouse_y = y;
+        self.a11y.pointer_used();
         
         // Update tab bar hover
         // Would need tabs reference - for now just track position
@@ -235,6 +448,22 @@ impl Chrome {
         self.url_bar.handle_char(c);
     }
     
+    /// Insert text committed by an input method into the URL bar
+    pub fn insert_text(&mut self, text: &str) {
+        self.url_bar.insert_text(text);
+    }
+    
+    /// Show text an input method is composing in the URL bar
+    pub fn set_composition(&mut self, composition: Option<Composition>) {
+        self.url_bar.composition = composition;
+    }
+    
+    /// Where the URL bar's input method composes, in window pixels
+    pub fn ime_area(&self) -> (i32, i32, u32, u32) {
+        let url_bar_y = self.height.saturating_sub(URL_BAR_HEIGHT) as usize;
+        self.url_bar.ime_area(url_bar_y, TAB_BAR_WIDTH as usize, self.width as usize)
+    }
+    
     /// Handle backspace in URL bar
     pub fn handle_backspace(&mut self) {
         self.url_bar.handle_backspace();
@@ -262,6 +491,21 @@ impl Chrome {
         self.url_bar.cursor_end();
     }
     
+    /// Move through URL bar suggestions (`1` down, `-1` up)
+    pub fn handle_up_down(&mut self, delta: i32) {
+        self.url_bar.select_suggestion(delta);
+    }
+    
+    /// Current URL bar input
+    pub fn url_input(&self) -> &str {
+        &self.url_bar.input
+    }
+    
+    /// Offer history autocomplete for the URL bar input
+    pub fn set_url_suggestions(&mut self, completion: Option<String>, suggestions: Vec<String>) {
+        self.url_bar.set_suggestions(completion, suggestions);
+    }
+    
     /// Handle Enter key - returns URL to navigate to
     pub fn handle_enter(&mut self) -> Option<String> {
         self.url_bar.submit()
@@ -0,0 +1,440 @@
+//! Context Menu
+//!
+//! Menu shown for a right click on page content. What was under the pointer
+//! decides the entries: a link, an image, a video or audio element, selected
+//! text or an editable field, with the page's own entries when there is
+//! nothing more specific. Extensions add items of their own.
+
+use fos_dom::url::Url;
+use fos_dom::{DomTree, NodeId};
+use crate::contenteditable::EditCommand;
+
+/// What a click landed on, from hit testing the rendered page
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HitTestResult {
+    /// URL of the page
+    pub page_url: String,
+    /// Innermost node under the pointer
+    pub node: Option<NodeId>,
+    /// Resolved `href` of the enclosing link
+    pub link_url: Option<String>,
+    /// Resolved `src` of an image
+    pub image_url: Option<String>,
+    /// Video or audio element and its resolved source
+    pub media: Option<(MediaKind, String)>,
+    /// Selected text, if the click was on the selection
+    pub selection_text: Option<String>,
+    /// Inside a text field or editable content
+    pub editable: bool,
+}
+
+/// Kind of media element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Video,
+    Audio,
+}
+
+impl HitTestResult {
+    /// A click on the page background
+    pub fn new(page_url: &str) -> Self {
+        Self { page_url: page_url.to_string(), ..Self::default() }
+    }
+    
+    /// A click on `node`: looks through it and its ancestors for the link,
+    /// image, media element and editable field it belongs to
+    pub fn at_node(tree: &DomTree, node: NodeId, page_url: &str) -> Self {
+        let mut hit = Self::new(page_url);
+        hit.node = Some(node);
+        let base = Url::parse(page_url).ok();
+        let resolve = |value: &str| match &base {
+            Some(base) => base.join(value.trim()).ok().map(|url| url.to_string()),
+            None => Url::parse(value.trim()).ok().map(|url| url.to_string()),
+        };
+        
+        let mut editable = None;
+        let mut id = node;
+        while id.is_valid() {
+            let Some(current) = tree.get(id) else { break };
+            if let Some(element) = current.as_element() {
+                let attr = |name: &str| element.attrs.iter()
+                    .find(|a| tree.resolve(a.name.local).eq_ignore_ascii_case(name))
+                    .map(|a| a.value.as_str());
+                let tag = tree.resolve(element.name.local).to_ascii_lowercase();
+                match tag.as_str() {
+                    "a" | "area" if hit.link_url.is_none() => hit.link_url = attr("href").and_then(resolve),
+                    "img" if hit.image_url.is_none() => hit.image_url = attr("src").and_then(resolve),
+                    "video" | "audio" if hit.media.is_none() => {
+                        let kind = if tag == "video" { MediaKind::Video } else { MediaKind::Audio };
+                        let src = attr("src").map(str::to_string).or_else(|| media_source(tree, id));
+                        hit.media = src.as_deref().and_then(resolve).map(|url| (kind, url));
+                    }
+                    _ => {}
+                }
+                if editable.is_none() {
+                    editable = match tag.as_str() {
+                        "textarea" => Some(true),
+                        "input" => Some(!matches!(
+                            attr("type").map(str::to_ascii_lowercase).as_deref(),
+                            Some("button" | "checkbox" | "color" | "file" | "hidden" | "image" | "radio" | "range" | "reset" | "submit")
+                        )),
+                        _ => attr("contenteditable").map(|v| !v.eq_ignore_ascii_case("false")),
+                    };
+                }
+            }
+            id = current.parent;
+        }
+        hit.editable = editable.unwrap_or(false);
+        hit
+    }
+    
+    /// Set the selected text the click landed on
+    pub fn with_selection(mut self, text: &str) -> Self {
+        self.selection_text = Some(text.to_string()).filter(|t| !t.trim().is_empty());
+        self
+    }
+    
+    /// Contexts the click falls in; `Page` only when there is nothing
+    /// more specific
+    pub fn contexts(&self) -> Vec<MenuContext> {
+        let mut contexts = Vec::new();
+        if self.link_url.is_some() {
+            contexts.push(MenuContext::Link);
+        }
+        if self.image_url.is_some() {
+            contexts.push(MenuContext::Image);
+        }
+        match self.media {
+            Some((MediaKind::Video, _)) => contexts.push(MenuContext::Video),
+            Some((MediaKind::Audio, _)) => contexts.push(MenuContext::Audio),
+            None => {}
+        }
+        if self.selection_text.is_some() {
+            contexts.push(MenuContext::Selection);
+        }
+        if self.editable {
+            contexts.push(MenuContext::Editable);
+        }
+        if contexts.is_empty() {
+            contexts.push(MenuContext::Page);
+        }
+        contexts
+    }
+}
+
+/// `src` of the first `<source>` child of a media element
+fn media_source(tree: &DomTree, media: NodeId) -> Option<String> {
+    tree.children(media).find_map(|(_, child)| {
+        let element = child.as_element()?;
+        if !tree.resolve(element.name.local).eq_ignore_ascii_case("source") {
+            return None;
+        }
+        element.attrs.iter()
+            .find(|a| tree.resolve(a.name.local).eq_ignore_ascii_case("src"))
+            .map(|a| a.value.clone())
+    })
+}
+
+/// Context an extension's menu item is shown in, as named by
+/// `contextMenus.create`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuContext {
+    All,
+    Page,
+    Link,
+    Image,
+    Video,
+    Audio,
+    Selection,
+    Editable,
+}
+
+impl MenuContext {
+    pub fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "all" => Self::All,
+            "page" => Self::Page,
+            "link" => Self::Link,
+            "image" => Self::Image,
+            "video" => Self::Video,
+            "audio" => Self::Audio,
+            "selection" => Self::Selection,
+            "editable" => Self::Editable,
+            _ => return None,
+        })
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Page => "page",
+            Self::Link => "link",
+            Self::Image => "image",
+            Self::Video => "video",
+            Self::Audio => "audio",
+            Self::Selection => "selection",
+            Self::Editable => "editable",
+        }
+    }
+    
+    /// Whether an item for this context is shown for `hit`
+    pub fn applies_to(&self, hit: &HitTestResult) -> bool {
+        *self == Self::All || hit.contexts().contains(self)
+    }
+}
+
+/// What choosing a menu item does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContextMenuAction {
+    OpenLinkInNewTab(String),
+    CopyLinkAddress(String),
+    SaveLink(String),
+    OpenImageInNewTab(String),
+    SaveImage(String),
+    CopyImageAddress(String),
+    SaveMedia(String),
+    CopyMediaAddress(String),
+    /// Editing command for the focused field
+    Edit(EditCommand),
+    /// Copy the selected text
+    Copy,
+    Reload,
+    InspectElement(NodeId),
+    /// An item an extension added
+    Extension {
+        ext_id: String,
+        item_id: String,
+    },
+}
+
+/// A menu item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextMenuItem {
+    pub label: String,
+    pub action: ContextMenuAction,
+    pub enabled: bool,
+}
+
+impl ContextMenuItem {
+    pub fn new(label: &str, action: ContextMenuAction) -> Self {
+        Self { label: label.to_string(), action, enabled: true }
+    }
+    
+    /// Shown greyed out when not `enabled`
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+}
+
+/// A line of the menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContextMenuEntry {
+    Item(ContextMenuItem),
+    Separator,
+}
+
+/// Command the browser carries out for a chosen item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContextMenuCommand {
+    OpenInNewTab(String),
+    Reload,
+    /// Run an editing command in the focused field
+    Edit(EditCommand),
+    /// Tell an extension its item was clicked
+    Extension {
+        ext_id: String,
+        item_id: String,
+    },
+}
+
+/// Menu model for a hit-test result
+#[derive(Debug, Clone)]
+pub struct ContextMenu {
+    hit: HitTestResult,
+    /// Groups of items, shown with separators between them
+    groups: Vec<Vec<ContextMenuItem>>,
+}
+
+impl ContextMenu {
+    /// Built-in items for what was clicked
+    pub fn new(hit: HitTestResult) -> Self {
+        let mut groups = Vec::new();
+        
+        if let Some(url) = &hit.link_url {
+            groups.push(vec![
+                ContextMenuItem::new("Open link in new tab", ContextMenuAction::OpenLinkInNewTab(url.clone())),
+                ContextMenuItem::new("Copy link address", ContextMenuAction::CopyLinkAddress(url.clone())),
+                ContextMenuItem::new("Save link as", ContextMenuAction::SaveLink(url.clone())),
+            ]);
+        }
+        if let Some(url) = &hit.image_url {
+            groups.push(vec![
+                ContextMenuItem::new("Open image in new tab", ContextMenuAction::OpenImageInNewTab(url.clone())),
+                ContextMenuItem::new("Save image as", ContextMenuAction::SaveImage(url.clone())),
+                ContextMenuItem::new("Copy image address", ContextMenuAction::CopyImageAddress(url.clone())),
+            ]);
+        }
+        if let Some((kind, url)) = &hit.media {
+            let (save, copy) = match kind {
+                MediaKind::Video => ("Save video as", "Copy video address"),
+                MediaKind::Audio => ("Save audio as", "Copy audio address"),
+            };
+            groups.push(vec![
+                ContextMenuItem::new(save, ContextMenuAction::SaveMedia(url.clone())),
+                ContextMenuItem::new(copy, ContextMenuAction::CopyMediaAddress(url.clone())),
+            ]);
+        }
+        
+        let selected = hit.selection_text.is_some();
+        if hit.editable {
+            groups.push(vec![
+                ContextMenuItem::new("Cut", ContextMenuAction::Edit(EditCommand::Cut)).with_enabled(selected),
+                ContextMenuItem::new("Copy", ContextMenuAction::Edit(EditCommand::Copy)).with_enabled(selected),
+                ContextMenuItem::new("Paste", ContextMenuAction::Edit(EditCommand::Paste)),
+                ContextMenuItem::new("Select all", ContextMenuAction::Edit(EditCommand::SelectAll)),
+            ]);
+        } else if selected {
+            groups.push(vec![ContextMenuItem::new("Copy", ContextMenuAction::Copy)]);
+        }
+        
+        if hit.contexts() == [MenuContext::Page] {
+            groups.push(vec![ContextMenuItem::new("Reload", ContextMenuAction::Reload)]);
+        }
+        if let Some(node) = hit.node {
+            groups.push(vec![ContextMenuItem::new("Inspect", ContextMenuAction::InspectElement(node))]);
+        }
+        
+        Self { hit, groups }
+    }
+    
+    /// Add extension items, in a group of their own above "Inspect"
+    pub fn with_extension_items(mut self, items: Vec<ContextMenuItem>) -> Self {
+        if items.is_empty() {
+            return self;
+        }
+        let inspect = self.groups.last()
+            .is_some_and(|group| matches!(group[0].action, ContextMenuAction::InspectElement(_)));
+        let at = if inspect { self.groups.len() - 1 } else { self.groups.len() };
+        self.groups.insert(at, items);
+        self
+    }
+    
+    /// What the menu was opened on
+    pub fn hit(&self) -> &HitTestResult {
+        &self.hit
+    }
+    
+    /// Items and the separators between groups, top to bottom
+    pub fn entries(&self) -> Vec<ContextMenuEntry> {
+        let mut entries = Vec::new();
+        for group in &self.groups {
+            if !entries.is_empty() {
+                entries.push(ContextMenuEntry::Separator);
+            }
+            entries.extend(group.iter().cloned().map(ContextMenuEntry::Item));
+        }
+        entries
+    }
+    
+    /// Items in menu order
+    pub fn items(&self) -> impl Iterator<Item = &ContextMenuItem> {
+        self.groups.iter().flatten()
+    }
+    
+    /// Carry out a chosen action: downloads start, addresses and selected
+    /// text go to the clipboard and the inspector opens on the element.
+    /// Returns what is left for the browser to do.
+    #[cfg(feature = "full")]
+    pub fn perform(
+        &self,
+        action: &ContextMenuAction,
+        downloads: &mut crate::downloads::DownloadManager,
+        clipboard: &crate::clipboard::Clipboard,
+        devtools: &mut crate::devtools::DevTools,
+    ) -> Option<ContextMenuCommand> {
+        use ContextMenuAction::*;
+        match action {
+            OpenLinkInNewTab(url) | OpenImageInNewTab(url) => return Some(ContextMenuCommand::OpenInNewTab(url.clone())),
+            SaveLink(url) | SaveImage(url) | SaveMedia(url) => {
+                downloads.start(url, None);
+            }
+            CopyLinkAddress(url) | CopyImageAddress(url) | CopyMediaAddress(url) => {
+                clipboard.write_text(url);
+            }
+            Copy => {
+                if let Some(text) = &self.hit.selection_text {
+                    clipboard.write_text(text);
+                }
+            }
+            Edit(command) => return Some(ContextMenuCommand::Edit(*command)),
+            Reload => return Some(ContextMenuCommand::Reload),
+            InspectElement(node) => {
+                if !devtools.is_open() {
+                    devtools.toggle();
+                }
+                devtools.set_panel(crate::devtools::DevToolsPanel::Elements);
+                devtools.select_element(node.index() as u64);
+            }
+            Extension { ext_id, item_id } => {
+                return Some(ContextMenuCommand::Extension { ext_id: ext_id.clone(), item_id: item_id.clone() });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_link_image_and_media() {
+        let document = fos_html::parse_with_url(
+            r#"<html><body><a href="/docs"><img id="logo" src="logo.png"></a>
+            <video id="clip"><source src="https://cdn.example/clip.webm"></video></body></html>"#,
+            "https://example.com/index.html",
+        );
+        let tree = document.tree();
+        
+        let logo = document.get_element_by_id("logo").unwrap();
+        let menu = ContextMenu::new(HitTestResult::at_node(tree, logo, "https://example.com/index.html"));
+        assert_eq!(menu.hit().contexts(), [MenuContext::Link, MenuContext::Image]);
+        let actions: Vec<_> = menu.items().map(|item| item.action.clone()).collect();
+        assert_eq!(actions[0], ContextMenuAction::OpenLinkInNewTab("https://example.com/docs".to_string()));
+        assert_eq!(actions[4], ContextMenuAction::SaveImage("https://example.com/logo.png".to_string()));
+        assert_eq!(actions.last(), Some(&ContextMenuAction::InspectElement(logo)));
+        assert!(!actions.contains(&ContextMenuAction::Reload));
+        assert_eq!(menu.entries().iter().filter(|e| **e == ContextMenuEntry::Separator).count(), 2);
+        
+        let clip = document.get_element_by_id("clip").unwrap();
+        let hit = HitTestResult::at_node(tree, clip, "https://example.com/index.html");
+        assert_eq!(hit.media, Some((MediaKind::Video, "https://cdn.example/clip.webm".to_string())));
+        
+        let page = ContextMenu::new(HitTestResult::new("https://example.com/"));
+        assert_eq!(page.items().map(|i| i.action.clone()).collect::<Vec<_>>(), [ContextMenuAction::Reload]);
+    }
+    
+    #[test]
+    fn test_editable_selection_and_extension_items() {
+        let document = fos_html::parse(r#"<html><body><div contenteditable><p id="text">Hello</p></div></body></html>"#);
+        let text = document.get_element_by_id("text").unwrap();
+        let hit = HitTestResult::at_node(document.tree(), text, "https://example.com/");
+        assert!(hit.editable);
+        
+        let menu = ContextMenu::new(hit.clone());
+        let cut = menu.items().find(|i| i.label == "Cut").unwrap();
+        assert!(!cut.enabled);
+        
+        let extension = ContextMenuItem::new("Translate", ContextMenuAction::Extension {
+            ext_id: "ext".to_string(),
+            item_id: "translate".to_string(),
+        });
+        let menu = ContextMenu::new(hit.with_selection("Hello")).with_extension_items(vec![extension.clone()]);
+        assert!(menu.items().find(|i| i.label == "Cut").unwrap().enabled);
+        assert!(MenuContext::Selection.applies_to(menu.hit()));
+        let entries = menu.entries();
+        assert_eq!(entries[entries.len() - 3], ContextMenuEntry::Item(extension));
+        assert_eq!(entries[entries.len() - 2], ContextMenuEntry::Separator);
+    }
+}
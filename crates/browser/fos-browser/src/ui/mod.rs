@@ -6,6 +6,8 @@ pub mod font;
 pub mod tab_bar;
 pub mod url_bar;
 pub mod chrome;
+pub mod context_menu;
 
-pub use chrome::Chrome;
+pub use chrome::{Chrome, ChromeCommand};
+pub use context_menu::{ContextMenu, ContextMenuAction, ContextMenuCommand, ContextMenuEntry, ContextMenuItem, HitTestResult, MenuContext};
 
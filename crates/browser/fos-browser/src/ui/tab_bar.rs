@@ -19,6 +19,7 @@ pub mod colors {
     pub const TEXT: u32 = 0xFFE0E0E0;         // White text
     pub const TEXT_DIM: u32 = 0xFF80A0A0;     // Dimmed teal text
     pub const ACCENT: u32 = 0xFF40C0C0;       // Bright teal accent
+    pub const CAPTURE: u32 = 0xFFE04040;      // Red capture indicator
 }
 
 /// Tab bar state
@@ -101,9 +102,29 @@ impl TabBar {
                 );
             }
             
+            // Audio indicator at the right edge (reserves room from the title)
+            let show_audio = tab.audible || tab.audio_muted;
+            if show_audio {
+                self.draw_audio_indicator(
+                    buffer,
+                    buffer_width,
+                    buffer_height,
+                    width - 14,
+                    y_offset + 8,
+                    tab.audio_muted,
+                );
+            }
+            
+            // Capture indicator left of the audio indicator
+            if tab.capturing {
+                let x = if show_audio { width - 24 } else { width - 12 };
+                self.draw_capture_indicator(buffer, buffer_width, buffer_height, x, y_offset + 9);
+            }
+            
             // Draw tab title (truncated to fit)
             let title = &tab.title;
-            let max_chars = ((TAB_BAR_WIDTH - 10) / 7) as usize; // ~7px per char
+            let reserved = 10 + if show_audio { 14 } else { 0 } + if tab.capturing { 10 } else { 0 };
+            let max_chars = ((TAB_BAR_WIDTH - reserved) / 7) as usize; // ~7px per char
             let display_title: String = if title.len() > max_chars {
                 format!("{}…", &title[..max_chars.saturating_sub(1)])
             } else {
@@ -148,6 +169,59 @@ impl TabBar {
         }
     }
     
+    /// Draw a small speaker glyph; muted tabs get a cross next to it
+    fn draw_audio_indicator(
+        &self,
+        buffer: &mut [u32],
+        buffer_width: usize,
+        buffer_height: usize,
+        x: usize,
+        y: i32,
+        muted: bool,
+    ) {
+        if y < 0 {
+            return;
+        }
+        let y = y as usize;
+        let color = if muted { colors::TEXT_DIM } else { colors::ACCENT };
+        
+        // Speaker body
+        self.draw_rect(buffer, buffer_width, buffer_height, x, y + 3, 2, 3, color);
+        // Cone widening to the right
+        for i in 0..4 {
+            self.draw_rect(buffer, buffer_width, buffer_height, x + 2 + i, y + 3 - i, 1, 3 + 2 * i, color);
+        }
+        
+        if muted {
+            for i in 0..5 {
+                self.draw_rect(buffer, buffer_width, buffer_height, x + 7 + i, y + 2 + i, 1, 1, color);
+                self.draw_rect(buffer, buffer_width, buffer_height, x + 11 - i, y + 2 + i, 1, 1, color);
+            }
+        } else {
+            // Sound wave
+            self.draw_rect(buffer, buffer_width, buffer_height, x + 8, y + 2, 1, 5, color);
+            self.draw_rect(buffer, buffer_width, buffer_height, x + 10, y, 1, 9, color);
+        }
+    }
+    
+    /// Draw a filled red dot marking a tab that is being shared
+    fn draw_capture_indicator(
+        &self,
+        buffer: &mut [u32],
+        buffer_width: usize,
+        buffer_height: usize,
+        x: usize,
+        y: i32,
+    ) {
+        if y < 0 {
+            return;
+        }
+        let y = y as usize;
+        // 6x6 dot with clipped corners
+        self.draw_rect(buffer, buffer_width, buffer_height, x + 1, y, 4, 6, colors::CAPTURE);
+        self.draw_rect(buffer, buffer_width, buffer_height, x, y + 1, 6, 4, colors::CAPTURE);
+    }
+    
     /// Draw a character (simple bitmap font)
     fn draw_char(
         &self,
@@ -2,12 +2,21 @@
 //!
 //! Bottom URL bar with navigation buttons.
 
+use std::ops::Range;
+
+use fos_dom::url::PublicSuffixList;
+
+use crate::ime::{Composition, UnderlineStyle};
+
 /// URL bar height in pixels
 pub const URL_BAR_HEIGHT: u32 = 32;
 
 /// Button size  
 pub const BUTTON_SIZE: u32 = 28;
 
+/// Height of a row in the suggestion list
+pub const SUGGESTION_HEIGHT: u32 = 20;
+
 /// Colors (ARGB format)
 pub mod colors {
     pub const BG: u32 = 0xFF1A1A1A;
@@ -17,6 +26,8 @@ pub mod colors {
     pub const BUTTON_HOVER: u32 = 0xFF2D2D2D;
     pub const BUTTON_DISABLED: u32 = 0xFF404040;
     pub const LOADING: u32 = 0xFF4A9EFF;
+    pub const SUGGESTION_BG: u32 = 0xFF142E2E;
+    pub const SUGGESTION_SELECTED: u32 = 0xFF2A5A5A;
 }
 
 /// URL bar state
@@ -26,7 +37,7 @@ pub struct UrlBar {
     pub input: String,
     /// Is focused
     pub focused: bool,
-    /// Cursor position
+    /// Cursor position, as a byte index into `input`
     pub cursor: usize,
     /// Hovered button
     hovered_button: Option<UrlBarButton>,
@@ -38,6 +49,15 @@ pub struct UrlBar {
     pub loading: bool,
     /// Loading progress (0.0 - 1.0)
     pub progress: f32,
+    /// Inline autocomplete shown after the input while the cursor is at
+    /// its end
+    pub completion: String,
+    /// History suggestions listed above the bar while editing
+    pub suggestions: Vec<String>,
+    /// Suggestion chosen with the arrow keys
+    selected: Option<usize>,
+    /// IME text being composed, shown underlined at the cursor
+    pub composition: Option<Composition>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -59,6 +79,10 @@ impl UrlBar {
             can_forward: false,
             loading: false,
             progress: 0.0,
+            completion: String::new(),
+            suggestions: Vec::new(),
+            selected: None,
+            composition: None,
         }
     }
     
@@ -74,20 +98,78 @@ impl UrlBar {
     pub fn handle_char(&mut self, c: char) {
         if self.focused {
             self.input.insert(self.cursor, c);
-            self.cursor += 1;
+            self.cursor += c.len_utf8();
+        }
+    }
+    
+    /// Insert text committed by an input method
+    pub fn insert_text(&mut self, text: &str) {
+        if self.focused {
+            self.completion.clear();
+            self.input.insert_str(self.cursor, text);
+            self.cursor += text.len();
+        }
+    }
+    
+    /// Offer autocomplete for the input: text completing it inline and
+    /// URLs to list. Completion only shows with the cursor at the end.
+    pub fn set_suggestions(&mut self, completion: Option<String>, suggestions: Vec<String>) {
+        self.completion = completion.filter(|_| self.cursor == self.input.len()).unwrap_or_default();
+        self.suggestions = suggestions;
+        self.selected = None;
+    }
+    
+    /// Drop autocomplete, e.g. after deleting text
+    pub fn clear_suggestions(&mut self) {
+        self.completion.clear();
+        self.suggestions.clear();
+        self.selected = None;
+    }
+    
+    /// Take the inline completion into the input; false if there was none
+    pub fn accept_completion(&mut self) -> bool {
+        if self.completion.is_empty() {
+            return false;
+        }
+        self.input.push_str(&std::mem::take(&mut self.completion));
+        self.cursor = self.input.len();
+        true
+    }
+    
+    /// Move the suggestion selection down (`1`) or up (`-1`), filling the
+    /// input with the selected URL
+    pub fn select_suggestion(&mut self, delta: i32) {
+        if self.suggestions.is_empty() {
+            return;
         }
+        let last = self.suggestions.len() as i32 - 1;
+        let index = match self.selected {
+            Some(i) => (i as i32 + delta).clamp(0, last),
+            None if delta > 0 => 0,
+            None => last,
+        } as usize;
+        self.selected = Some(index);
+        self.input = self.suggestions[index].clone();
+        self.cursor = self.input.len();
+        self.completion.clear();
     }
     
     /// Handle backspace
     pub fn handle_backspace(&mut self) {
+        // Backspace first removes the inline completion
+        if !self.completion.is_empty() {
+            self.completion.clear();
+            return;
+        }
         if self.focused && self.cursor > 0 {
-            self.cursor -= 1;
+            self.cursor = self.previous_boundary();
             self.input.remove(self.cursor);
         }
     }
     
     /// Handle delete
     pub fn handle_delete(&mut self) {
+        self.completion.clear();
         if self.focused && self.cursor < self.input.len() {
             self.input.remove(self.cursor);
         }
@@ -95,31 +177,37 @@ impl UrlBar {
     
     /// Move cursor left
     pub fn cursor_left(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
-        }
+        self.completion.clear();
+        self.cursor = self.previous_boundary();
     }
     
-    /// Move cursor right
+    /// Move cursor right, accepting the inline completion at the end
     pub fn cursor_right(&mut self) {
-        if self.cursor < self.input.len() {
-            self.cursor += 1;
+        if self.accept_completion() {
+            return;
+        }
+        if let Some(c) = self.input[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
         }
     }
     
     /// Move cursor to start
     pub fn cursor_home(&mut self) {
+        self.completion.clear();
         self.cursor = 0;
     }
     
-    /// Move cursor to end
+    /// Move cursor to end, accepting the inline completion
     pub fn cursor_end(&mut self) {
+        self.accept_completion();
         self.cursor = self.input.len();
     }
     
-    /// Submit the URL (returns URL to navigate to)
+    /// Submit the URL with any inline completion (returns URL to navigate to)
     pub fn submit(&mut self) -> Option<String> {
         if self.focused && !self.input.is_empty() {
+            self.accept_completion();
+            self.clear_suggestions();
             self.focused = false;
             Some(self.input.clone())
         } else {
@@ -136,6 +224,28 @@ impl UrlBar {
     /// Cancel editing
     pub fn unfocus(&mut self) {
         self.focused = false;
+        self.composition = None;
+        self.clear_suggestions();
+    }
+    
+    /// Byte index of the character before the cursor
+    fn previous_boundary(&self) -> usize {
+        self.input[..self.cursor].char_indices().next_back().map_or(0, |(i, _)| i)
+    }
+    
+    /// Characters drawn before the composition: the input up to the cursor
+    fn cursor_chars(&self) -> usize {
+        self.input[..self.cursor].chars().count()
+    }
+    
+    /// Where the IME composes, in window pixels, for placing its candidate
+    /// window: the composition, or the cursor before one starts
+    pub fn ime_area(&self, y_start: usize, x_start: usize, buffer_width: usize) -> (i32, i32, u32, u32) {
+        let max_chars = buffer_width.saturating_sub(x_start + 16) / 7;
+        let start = self.cursor_chars().min(max_chars);
+        let length = self.composition.as_ref().map_or(0, |c| c.text.chars().count());
+        let width = (length.min(max_chars - start) * 7).max(1);
+        ((x_start + 8 + start * 7) as i32, (y_start + 4) as i32, width as u32, URL_BAR_HEIGHT - 8)
     }
     
     /// Render the URL bar - simplified, keyboard-only (no buttons)
@@ -169,22 +279,94 @@ impl UrlBar {
         let available_width = width.saturating_sub(16);
         let max_chars = available_width / 7;
         
-        let display_url: String = if self.input.len() > max_chars {
-            format!("{}…", &self.input[..max_chars.saturating_sub(1)])
+        // Text being composed shows inside the input at the cursor
+        let composition = self.composition.as_ref().filter(|_| self.focused);
+        let shown = match composition {
+            Some(composition) => {
+                let mut shown = self.input.clone();
+                shown.insert_str(self.cursor, &composition.text);
+                shown
+            }
+            None => self.input.clone(),
+        };
+        let display_url: String = if shown.chars().count() > max_chars {
+            let kept: String = shown.chars().take(max_chars.saturating_sub(1)).collect();
+            format!("{}…", kept)
         } else {
-            self.input.clone()
+            shown
         };
         
-        self.draw_text(
-            buffer, buffer_width, buffer_height,
-            x_start + 8, y_start + height / 2 - 4,
-            &display_url,
-            text_color,
-        );
+        // Unfocused, the registrable domain stands out from the rest
+        let text_y = y_start + height / 2 - 4;
+        match self.site_range().filter(|_| !self.focused) {
+            Some(site) => {
+                let chars: Vec<char> = display_url.chars().collect();
+                let site = site.start.min(chars.len())..site.end.min(chars.len());
+                let segments = [
+                    (0..site.start, colors::TEXT_DIM),
+                    (site.clone(), colors::TEXT),
+                    (site.end..chars.len(), colors::TEXT_DIM),
+                ];
+                for (range, color) in segments {
+                    let segment: String = chars[range.clone()].iter().collect();
+                    self.draw_text(
+                        buffer, buffer_width, buffer_height,
+                        x_start + 8 + range.start * 7, text_y,
+                        &segment,
+                        color,
+                    );
+                }
+            }
+            None => self.draw_text(
+                buffer, buffer_width, buffer_height,
+                x_start + 8, text_y,
+                &display_url,
+                text_color,
+            ),
+        }
+        
+        // Inline completion, dimmed, after the input
+        let input_chars = display_url.chars().count();
+        if self.focused && composition.is_none() && !self.completion.is_empty() && input_chars < max_chars {
+            let completion: String = self.completion.chars().take(max_chars - input_chars).collect();
+            self.draw_text(
+                buffer, buffer_width, buffer_height,
+                x_start + 8 + input_chars * 7, text_y,
+                &completion,
+                colors::TEXT_DIM,
+            );
+        }
+        
+        if self.focused {
+            self.render_suggestions(buffer, buffer_width, buffer_height, y_start, x_start, max_chars);
+        }
+        
+        // Underline the composition: thin under all of it, thick under the
+        // clause being converted
+        let cursor_chars = self.cursor_chars();
+        if let Some(composition) = composition {
+            for (range, style) in composition.underlines() {
+                let thickness = match style {
+                    UnderlineStyle::Thin => 1,
+                    UnderlineStyle::Thick => 2,
+                };
+                let start = (cursor_chars + range.start).min(max_chars);
+                let end = (cursor_chars + range.end).min(max_chars);
+                for py in (text_y + 10..text_y + 10 + thickness).filter(|&py| py < buffer_height) {
+                    // Gaps between clauses keep them apart
+                    for px in (x_start + 8 + start * 7)..(x_start + 8 + end * 7).saturating_sub(1) {
+                        if px < buffer_width {
+                            buffer[py * buffer_width + px] = text_color;
+                        }
+                    }
+                }
+            }
+        }
         
         // Draw cursor if focused
         if self.focused {
-            let visible_cursor = self.cursor.min(max_chars);
+            let caret = cursor_chars + composition.map_or(0, |c| c.caret());
+            let visible_cursor = caret.min(max_chars);
             let cursor_x = x_start + 8 + visible_cursor * 7;
             for dy in 4..height - 4 {
                 let py = y_start + dy;
@@ -207,6 +389,30 @@ impl UrlBar {
         }
     }
     
+    /// List suggestions in rows above the bar, the best nearest to it
+    fn render_suggestions(
+        &self,
+        buffer: &mut [u32],
+        buffer_width: usize,
+        buffer_height: usize,
+        y_start: usize,
+        x_start: usize,
+        max_chars: usize,
+    ) {
+        let row_height = SUGGESTION_HEIGHT as usize;
+        for (i, url) in self.suggestions.iter().enumerate() {
+            let Some(row_y) = y_start.checked_sub((i + 1) * row_height) else { break };
+            let bg = if self.selected == Some(i) { colors::SUGGESTION_SELECTED } else { colors::SUGGESTION_BG };
+            for py in row_y..(row_y + row_height).min(buffer_height) {
+                for px in x_start..buffer_width {
+                    buffer[py * buffer_width + px] = bg;
+                }
+            }
+            let text: String = url.chars().take(max_chars).collect();
+            self.draw_text(buffer, buffer_width, buffer_height, x_start + 8, row_y + row_height / 2 - 4, &text, colors::TEXT);
+        }
+    }
+    
     /// Draw a button
     #[allow(dead_code)]
     fn draw_button(
@@ -262,6 +468,20 @@ impl UrlBar {
         super::font::draw_char(buffer, buffer_width, buffer_height, x, y, c, color);
     }
     
+    /// Character range of the registrable domain in the input, if it is a
+    /// URL with a domain host
+    fn site_range(&self) -> Option<Range<usize>> {
+        let (_, rest) = self.input.split_once("://")?;
+        let host_start = self.input.len() - rest.len();
+        let authority = rest.split(['/', '?', '#']).next()?;
+        let host = authority.rsplit('@').next()?;
+        let host = host.split(':').next()?;
+        let host_start = host_start + authority.len() - authority.rsplit('@').next()?.len();
+        let range = PublicSuffixList::current().registrable_domain_range(host)?;
+        let to_chars = |byte: usize| self.input[..host_start + byte].chars().count();
+        Some(to_chars(range.start)..to_chars(range.end))
+    }
+    
     /// Draw text
     fn draw_text(
         &self,
@@ -339,7 +559,7 @@ impl UrlBar {
             self.focus();
             // Approximate cursor position from click
             let char_offset = ((local_x - input_start - 8) / 7).max(0) as usize;
-            self.cursor = char_offset.min(self.input.len());
+            self.cursor = self.input.char_indices().nth(char_offset).map_or(self.input.len(), |(i, _)| i);
             return Some(UrlBarAction::Focus);
         } else {
             self.focused = false;
@@ -367,3 +587,55 @@ pub enum UrlBarAction {
     Menu,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_autocomplete() {
+        let mut bar = UrlBar::new();
+        bar.focus();
+        bar.handle_char('r');
+        bar.handle_char('u');
+        bar.set_suggestions(Some("st-lang.org/".into()), vec!["https://www.rust-lang.org/".into(), "https://docs.rs/".into()]);
+        assert_eq!(bar.completion, "st-lang.org/");
+        
+        // Backspace drops the completion before any typed text
+        bar.handle_backspace();
+        assert_eq!((bar.input.as_str(), bar.completion.as_str()), ("ru", ""));
+        bar.set_suggestions(Some("st-lang.org/".into()), vec![]);
+        assert_eq!(bar.submit().as_deref(), Some("rust-lang.org/"));
+        
+        bar.focus();
+        bar.set_suggestions(None, vec!["https://a.com/".into(), "https://b.com/".into()]);
+        bar.select_suggestion(1);
+        bar.select_suggestion(1);
+        bar.select_suggestion(1);
+        assert_eq!(bar.input, "https://b.com/");
+        bar.select_suggestion(-1);
+        assert_eq!(bar.submit().as_deref(), Some("https://a.com/"));
+        assert!(bar.suggestions.is_empty());
+    }
+    
+    #[test]
+    fn test_ime_text() {
+        let mut bar = UrlBar::new();
+        bar.focus();
+        bar.handle_char('é');
+        bar.insert_text("日本語");
+        bar.cursor_left();
+        bar.handle_backspace();
+        assert_eq!(bar.input, "é日語");
+        bar.cursor_right();
+        assert_eq!(bar.cursor, bar.input.len());
+        
+        bar.composition = Some(Composition { text: "にほ".into(), selection: None });
+        let (x, _, width, _) = bar.ime_area(0, 0, 800);
+        assert_eq!((x, width), (8 + 3 * 7, 14));
+        let mut buffer = vec![0; 800 * URL_BAR_HEIGHT as usize];
+        bar.render(&mut buffer, 800, URL_BAR_HEIGHT as usize, 0, 0);
+        bar.unfocus();
+        assert!(bar.composition.is_none());
+    }
+}
@@ -0,0 +1,448 @@
+//! Installed Web Apps
+//!
+//! Sites with a web app manifest can be installed. An installed app gets a
+//! launcher entry whose actions are the manifest's shortcuts, the jump list
+//! on platforms that have one, and pages in its scope can badge its icon
+//! with navigator.setAppBadge. Installed apps are saved to disk; badges
+//! only last for the session.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use fos_devtools::WebAppManifest;
+use fos_dom::url::Url;
+use fos_dom::{Document, DomTree, NodeId};
+use fos_js::AppBadge;
+
+/// Most shortcuts offered for one app; launchers only show a few
+pub const MAX_SHORTCUTS: usize = 10;
+
+/// Shortcut into an installed app, from the manifest's `shortcuts`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppShortcut {
+    pub name: String,
+    pub description: Option<String>,
+    /// Absolute URL, within the app's scope
+    pub url: String,
+    /// Absolute icon URL
+    pub icon: Option<String>,
+}
+
+/// An installed web app
+#[derive(Debug, Clone)]
+pub struct InstalledApp {
+    /// Identifies the app: its absolute start URL
+    pub id: String,
+    pub name: String,
+    pub manifest_url: String,
+    pub start_url: String,
+    /// Pages whose URL starts with this belong to the app
+    pub scope: String,
+    /// Absolute URL of the largest icon
+    pub icon: Option<String>,
+    pub shortcuts: Vec<AppShortcut>,
+    pub badge: AppBadge,
+    /// Manifest as fetched, saved so the app can be restored
+    manifest_json: String,
+}
+
+impl InstalledApp {
+    /// Read an app from its manifest, fetched from `manifest_url` for the
+    /// page at `document_url`. URLs resolve against the manifest; the start
+    /// URL must be same-origin with the page and shortcuts outside the
+    /// scope are dropped.
+    pub fn from_manifest(document_url: &str, manifest_url: &str, json: &str) -> Result<Self, WebAppError> {
+        let manifest = WebAppManifest::from_json(json).ok_or(WebAppError::InvalidManifest)?;
+        let name = manifest.name.clone().or(manifest.short_name.clone())
+            .filter(|name| !name.trim().is_empty())
+            .ok_or(WebAppError::MissingName)?;
+        let base = Url::parse(manifest_url).map_err(|_| WebAppError::InvalidUrl(manifest_url.to_string()))?;
+        let document = Url::parse(document_url).map_err(|_| WebAppError::InvalidUrl(document_url.to_string()))?;
+        let resolve = |url: &str| base.join(url).ok();
+        
+        let start = manifest.start_url.as_deref()
+            .and_then(resolve)
+            .filter(|start| start.origin() == document.origin())
+            .unwrap_or_else(|| document.clone());
+        let start_url = start.as_str();
+        
+        // The scope must contain the start URL; by default it is the start
+        // URL's directory
+        let default_scope = format!("{}{}", start.origin(), &start.path()[..=start.path().rfind('/').unwrap_or(0)]);
+        let scope = manifest.scope.as_deref()
+            .and_then(resolve)
+            .map(|scope| format!("{}{}", scope.origin(), scope.path()))
+            .filter(|scope| start_url.starts_with(scope.as_str()))
+            .unwrap_or(default_scope);
+        
+        let icon = manifest.icons.iter()
+            .max_by_key(|icon| icon.sizes.iter().map(|(w, h)| w * h).max().unwrap_or(0))
+            .and_then(|icon| resolve(&icon.src))
+            .map(|url| url.as_str());
+        let shortcuts = manifest.shortcuts.iter()
+            .filter_map(|shortcut| Some(AppShortcut {
+                name: shortcut.name.clone(),
+                description: shortcut.description.clone(),
+                url: resolve(&shortcut.url).map(|url| url.as_str()).filter(|url| url.starts_with(&scope))?,
+                icon: shortcut.icons.first().and_then(|icon| resolve(&icon.src)).map(|url| url.as_str()),
+            }))
+            .take(MAX_SHORTCUTS)
+            .collect();
+        
+        Ok(Self {
+            id: start_url.clone(),
+            name,
+            manifest_url: manifest_url.to_string(),
+            start_url,
+            scope,
+            icon,
+            shortcuts,
+            badge: AppBadge::None,
+            manifest_json: json.to_string(),
+        })
+    }
+    
+    /// Whether the page at `url` belongs to this app
+    pub fn in_scope(&self, url: &str) -> bool {
+        url.starts_with(&self.scope)
+    }
+}
+
+/// Why an app could not be installed
+#[derive(Debug, thiserror::Error)]
+pub enum WebAppError {
+    #[error("Manifest is not a JSON object")]
+    InvalidManifest,
+    
+    #[error("Manifest has no name")]
+    MissingName,
+    
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+}
+
+/// Platform launcher installed apps appear in, implemented per platform
+pub trait AppLauncher: Send + std::fmt::Debug {
+    /// Add or update the app's launcher entry and its shortcuts
+    fn register(&mut self, app: &InstalledApp) -> io::Result<()>;
+    
+    /// Remove the app's launcher entry
+    fn unregister(&mut self, app: &InstalledApp) -> io::Result<()>;
+    
+    /// Show `badge` on the app's icon
+    fn set_badge(&mut self, app: &InstalledApp, badge: AppBadge);
+}
+
+/// Installed web apps
+#[derive(Debug, Default)]
+pub struct WebApps {
+    apps: Vec<InstalledApp>,
+    launcher: Option<Box<dyn AppLauncher>>,
+    storage_path: Option<PathBuf>,
+}
+
+impl WebApps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Create with persistence
+    pub fn with_storage(path: PathBuf) -> Self {
+        let mut apps = Self::new();
+        apps.storage_path = Some(path);
+        apps.load();
+        apps
+    }
+    
+    /// Surface apps in `launcher`, refreshing the entries of those already
+    /// installed
+    pub fn set_launcher(&mut self, mut launcher: Box<dyn AppLauncher>) {
+        for app in &self.apps {
+            if let Err(e) = launcher.register(app) {
+                log::warn!("Failed to register web app {}: {}", app.name, e);
+            }
+        }
+        self.launcher = Some(launcher);
+    }
+    
+    pub fn apps(&self) -> &[InstalledApp] {
+        &self.apps
+    }
+    
+    /// Install the app described by a manifest, or update it when it is
+    /// installed already
+    pub fn install(&mut self, document_url: &str, manifest_url: &str, json: &str) -> Result<&InstalledApp, WebAppError> {
+        let mut app = InstalledApp::from_manifest(document_url, manifest_url, json)?;
+        let index = match self.apps.iter().position(|a| a.id == app.id) {
+            Some(i) => {
+                app.badge = self.apps[i].badge;
+                self.apps[i] = app;
+                i
+            }
+            None => {
+                self.apps.push(app);
+                self.apps.len() - 1
+            }
+        };
+        if let Some(launcher) = &mut self.launcher {
+            if let Err(e) = launcher.register(&self.apps[index]) {
+                log::warn!("Failed to register web app {}: {}", self.apps[index].name, e);
+            }
+        }
+        self.save();
+        Ok(&self.apps[index])
+    }
+    
+    /// Uninstall an app; false if it was not installed
+    pub fn uninstall(&mut self, id: &str) -> bool {
+        let Some(index) = self.apps.iter().position(|a| a.id == id) else { return false };
+        let app = self.apps.remove(index);
+        if let Some(launcher) = &mut self.launcher {
+            if let Err(e) = launcher.unregister(&app) {
+                log::warn!("Failed to unregister web app {}: {}", app.name, e);
+            }
+        }
+        self.save();
+        true
+    }
+    
+    /// Installed app the page at `url` belongs to; the narrowest scope wins
+    pub fn app_for_url(&self, url: &str) -> Option<&InstalledApp> {
+        self.apps.iter()
+            .filter(|app| app.in_scope(url))
+            .max_by_key(|app| app.scope.len())
+    }
+    
+    /// Badge the app of the page at `page_url`. Pages outside any
+    /// installed app can't badge; returns whether the badge changed.
+    pub fn set_badge(&mut self, page_url: &str, badge: AppBadge) -> bool {
+        let Some(id) = self.app_for_url(page_url).map(|app| app.id.clone()) else { return false };
+        let Some(app) = self.apps.iter_mut().find(|app| app.id == id) else { return false };
+        if app.badge == badge {
+            return false;
+        }
+        app.badge = badge;
+        if let Some(launcher) = &mut self.launcher {
+            launcher.set_badge(app, badge);
+        }
+        true
+    }
+    
+    /// Save to disk
+    pub fn save(&self) {
+        let Some(path) = &self.storage_path else { return };
+        
+        // JSON needs no line breaks outside strings, and has none in them
+        let mut data = String::new();
+        for app in &self.apps {
+            let json = app.manifest_json.lines().collect::<Vec<_>>().join(" ");
+            data.push_str(&format!("{}\t{}\t{}\n", app.start_url, app.manifest_url, json));
+        }
+        
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = fs::write(path, data) {
+            log::warn!("Failed to save web apps to {}: {}", path.display(), e);
+        }
+    }
+    
+    /// Load from disk
+    pub fn load(&mut self) {
+        let Some(path) = &self.storage_path else { return };
+        
+        let data = match fs::read_to_string(path) {
+            Ok(d) => d,
+            Err(_) => return,
+        };
+        
+        for line in data.lines() {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(start_url), Some(manifest_url), Some(json)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            if let Ok(app) = InstalledApp::from_manifest(start_url, manifest_url, json) {
+                self.apps.push(app);
+            }
+        }
+    }
+}
+
+/// `href` of the document's first `<link rel="manifest">`, as written
+pub fn manifest_link(document: &Document) -> Option<String> {
+    let tree = document.tree();
+    find_manifest_link(tree, tree.root())
+}
+
+fn find_manifest_link(tree: &DomTree, node_id: NodeId) -> Option<String> {
+    if !node_id.is_valid() {
+        return None;
+    }
+    
+    if let Some(element) = tree.get(node_id).and_then(|node| node.as_element()) {
+        if tree.resolve(element.name.local).eq_ignore_ascii_case("link") {
+            let attr = |name: &str| element.attrs.iter()
+                .find(|attr| tree.resolve(attr.name.local).eq_ignore_ascii_case(name))
+                .map(|attr| attr.value.as_str());
+            let is_manifest = attr("rel").is_some_and(|rel| {
+                rel.split_ascii_whitespace().any(|r| r.eq_ignore_ascii_case("manifest"))
+            });
+            if let Some(href) = attr("href").filter(|_| is_manifest) {
+                return Some(href.trim().to_string());
+            }
+        }
+    }
+    
+    tree.children(node_id).find_map(|(child_id, _)| find_manifest_link(tree, child_id))
+}
+
+/// Launcher for freedesktop desktops: a desktop entry per app, with its
+/// shortcuts as desktop actions. Desktop entries carry no badge, so the
+/// browser shows it in the window title instead.
+#[derive(Debug)]
+pub struct DesktopEntries {
+    /// Directory entries are written to
+    dir: PathBuf,
+    /// Browser executable entries launch
+    exec: PathBuf,
+}
+
+impl DesktopEntries {
+    pub fn new(dir: PathBuf, exec: PathBuf) -> Self {
+        Self { dir, exec }
+    }
+    
+    /// The user's applications directory
+    pub fn default_dir() -> Option<PathBuf> {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+        Some(data_home.join("applications"))
+    }
+    
+    /// Entry file for `app`, named after its start URL
+    fn entry_path(&self, app: &InstalledApp) -> PathBuf {
+        let name: String = app.id.split_once("://").map_or(app.id.as_str(), |(_, rest)| rest)
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' })
+            .collect();
+        self.dir.join(format!("fos-app-{}.desktop", name.trim_matches('-')))
+    }
+    
+    /// Desktop entry launching `app`, its shortcuts as actions
+    pub fn desktop_entry(&self, app: &InstalledApp) -> String {
+        let mut entry = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec={}\nTerminal=false\n",
+            entry_value(&app.name),
+            self.exec_line(&app.start_url),
+        );
+        if !app.shortcuts.is_empty() {
+            let actions: Vec<String> = (0..app.shortcuts.len()).map(|i| format!("shortcut-{};", i)).collect();
+            entry.push_str(&format!("Actions={}\n", actions.concat()));
+        }
+        for (i, shortcut) in app.shortcuts.iter().enumerate() {
+            entry.push_str(&format!(
+                "\n[Desktop Action shortcut-{}]\nName={}\nExec={}\n",
+                i,
+                entry_value(&shortcut.name),
+                self.exec_line(&shortcut.url),
+            ));
+        }
+        entry
+    }
+    
+    /// Exec value opening `url`, quoted as the spec asks
+    fn exec_line(&self, url: &str) -> String {
+        let quote = |arg: &str| {
+            let mut quoted = String::from("\"");
+            for c in arg.chars() {
+                match c {
+                    '"' | '`' | '$' | '\\' => { quoted.push('\\'); quoted.push(c); }
+                    // Field codes start with %
+                    '%' => quoted.push_str("%%"),
+                    c => quoted.push(c),
+                }
+            }
+            quoted.push('"');
+            quoted
+        };
+        // Values are escaped again as strings
+        entry_value(&format!("{} {}", quote(&self.exec.to_string_lossy()), quote(url)))
+    }
+}
+
+/// Escape a desktop entry string value
+fn entry_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n").replace('\t', "\\t").replace('\r', "\\r")
+}
+
+impl AppLauncher for DesktopEntries {
+    fn register(&mut self, app: &InstalledApp) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.entry_path(app), self.desktop_entry(app))
+    }
+    
+    fn unregister(&mut self, app: &InstalledApp) -> io::Result<()> {
+        match fs::remove_file(self.entry_path(app)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+    
+    fn set_badge(&mut self, app: &InstalledApp, badge: AppBadge) {
+        log::debug!("Badge for {}: {:?}", app.name, badge);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const MANIFEST: &str = r#"{
+        "name": "Mail", "start_url": "/mail/inbox?src=pwa", "scope": "/mail/",
+        "icons": [{"src": "/i/48.png", "sizes": "48x48"}, {"src": "/i/192.png", "sizes": "192x192"}],
+        "shortcuts": [
+            {"name": "Compose", "url": "compose", "icons": [{"src": "/i/compose.png"}]},
+            {"name": "Elsewhere", "url": "/blog/"}
+        ]
+    }"#;
+    
+    #[test]
+    fn test_install_and_badge() {
+        let path = std::env::temp_dir().join(format!("fos-web-apps-test-{}", std::process::id()));
+        let mut apps = WebApps::with_storage(path.clone());
+        let app = apps.install("https://example.com/mail/", "https://example.com/mail/app.webmanifest", MANIFEST).unwrap();
+        assert_eq!(app.start_url, "https://example.com/mail/inbox?src=pwa");
+        assert_eq!(app.scope, "https://example.com/mail/");
+        assert_eq!(app.icon.as_deref(), Some("https://example.com/i/192.png"));
+        assert_eq!(app.shortcuts.len(), 1);
+        assert_eq!(app.shortcuts[0].url, "https://example.com/mail/compose");
+        
+        assert!(apps.set_badge("https://example.com/mail/thread/1", AppBadge::Count(3)));
+        assert!(!apps.set_badge("https://example.com/blog/", AppBadge::Flag));
+        assert_eq!(apps.app_for_url("https://example.com/mail/").unwrap().badge, AppBadge::Count(3));
+        
+        // Restored apps come back without their badge
+        let restored = WebApps::with_storage(path.clone());
+        assert_eq!(restored.apps().len(), 1);
+        assert_eq!(restored.apps()[0].badge, AppBadge::None);
+        
+        assert!(matches!(apps.install("https://example.com/", "https://example.com/m.json", "{}"), Err(WebAppError::MissingName)));
+        assert!(apps.uninstall("https://example.com/mail/inbox?src=pwa"));
+        assert!(WebApps::with_storage(path.clone()).apps().is_empty());
+        let _ = fs::remove_file(path);
+    }
+    
+    #[test]
+    fn test_desktop_entry() {
+        let app = InstalledApp::from_manifest("https://example.com/mail/", "https://example.com/mail/m.json", MANIFEST).unwrap();
+        let launcher = DesktopEntries::new(PathBuf::from("/tmp/apps"), PathBuf::from("/usr/bin/fos browser"));
+        let entry = launcher.desktop_entry(&app);
+        assert!(entry.contains("Name=Mail\n"));
+        assert!(entry.contains("Exec=\"/usr/bin/fos browser\" \"https://example.com/mail/inbox?src=pwa\"\n"));
+        assert!(entry.contains("Actions=shortcut-0;\n"));
+        assert!(entry.contains("[Desktop Action shortcut-0]\nName=Compose\n"));
+        assert_eq!(launcher.entry_path(&app), PathBuf::from("/tmp/apps/fos-app-example.com-mail-inbox-src-pwa.desktop"));
+    }
+}
@@ -0,0 +1,312 @@
+//! WebOTP API
+//!
+//! `navigator.credentials.get({ otp: { transport: ["sms"] } })`. One-time
+//! codes come from origin-bound SMS messages whose last line names the site:
+//!
+//! ```text
+//! Your code is 123456.
+//!
+//! @www.example.com #123456
+//! ```
+//!
+//! A cross-origin iframe is addressed as `@top.example #123456 @embedded.example`.
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// OTP error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OtpError {
+    /// No SMS retriever on this platform
+    NotSupported,
+    /// No user activation, or the origin is blocked
+    NotAllowed,
+    /// Aborted by the page, superseded by a newer request, or declined by the user
+    Aborted,
+    /// No matching SMS arrived in time
+    Timeout,
+}
+
+impl std::fmt::Display for OtpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotSupported => write!(f, "WebOTP not supported"),
+            Self::NotAllowed => write!(f, "OTP retrieval not allowed"),
+            Self::Aborted => write!(f, "OTP request aborted"),
+            Self::Timeout => write!(f, "OTP request timed out"),
+        }
+    }
+}
+
+impl std::error::Error for OtpError {}
+
+/// Platform SMS source, implemented by embedders (e.g. Android SMS User
+/// Consent / SMS Retriever)
+pub trait SmsRetriever: Send + std::fmt::Debug {
+    /// Start listening for incoming messages
+    fn start(&mut self);
+
+    /// Next message body received since the last call
+    fn poll(&mut self) -> Option<String>;
+
+    fn stop(&mut self);
+}
+
+/// Origin-bound code parsed from an SMS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginBoundCode {
+    pub host: String,
+    pub code: String,
+    /// Host of the embedded frame the code is for
+    pub embedded_host: Option<String>,
+}
+
+impl OriginBoundCode {
+    /// Parse the last line of an SMS
+    pub fn parse(sms: &str) -> Option<Self> {
+        let line = sms.trim_end().lines().last()?;
+        let mut tokens = line.split_whitespace();
+        let host = tokens.next()?.strip_prefix('@')?;
+        let code = tokens.next()?.strip_prefix('#')?;
+        let embedded_host = match tokens.next() {
+            Some(token) => Some(token.strip_prefix('@')?.to_string()),
+            None => None,
+        };
+        if host.is_empty() || code.is_empty() || tokens.next().is_some() {
+            return None;
+        }
+        Some(Self { host: host.to_string(), code: code.to_string(), embedded_host })
+    }
+}
+
+/// Host of an origin ("https://example.com:443" -> "example.com")
+fn origin_host(origin: &str) -> &str {
+    let rest = origin.split_once("://").map(|(_, r)| r).unwrap_or(origin);
+    let host = rest.split('/').next().unwrap_or(rest);
+    host.rsplit_once(':').map(|(h, _)| h).unwrap_or(host)
+}
+
+/// Pending `credentials.get()` call
+#[derive(Debug, Clone)]
+pub struct OtpRequest {
+    pub id: u64,
+    pub origin: String,
+    /// Top-level origin when requested from an iframe
+    pub top_origin: Option<String>,
+    pub started: Instant,
+    /// Code waiting for the user's consent
+    pub code: Option<String>,
+}
+
+impl OtpRequest {
+    fn matches(&self, code: &OriginBoundCode) -> bool {
+        match (&self.top_origin, &code.embedded_host) {
+            (None, None) => origin_host(&self.origin) == code.host,
+            (Some(top), Some(embedded)) => {
+                origin_host(top) == code.host && origin_host(&self.origin) == embedded
+            }
+            _ => false,
+        }
+    }
+}
+
+/// OTP event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OtpEvent {
+    /// Resolve with an OTPCredential
+    Resolved { request_id: u64, code: String },
+    Rejected { request_id: u64, error: OtpError },
+}
+
+/// WebOTP manager
+#[derive(Debug)]
+pub struct OtpManager {
+    retriever: Option<Box<dyn SmsRetriever>>,
+    /// Only one request is pending at a time
+    pending: Option<OtpRequest>,
+    blocked_origins: HashSet<String>,
+    /// How long a request waits for an SMS
+    pub timeout: Duration,
+    events: Vec<OtpEvent>,
+    next_id: u64,
+}
+
+impl Default for OtpManager {
+    fn default() -> Self {
+        Self {
+            retriever: None,
+            pending: None,
+            blocked_origins: HashSet::new(),
+            timeout: Duration::from_secs(5 * 60),
+            events: Vec::new(),
+            next_id: 1,
+        }
+    }
+}
+
+impl OtpManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_retriever(&mut self, retriever: Box<dyn SmsRetriever>) {
+        self.retriever = Some(retriever);
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.retriever.is_some()
+    }
+
+    /// Block or unblock an origin (site settings)
+    pub fn set_origin_blocked(&mut self, origin: &str, blocked: bool) {
+        if blocked {
+            self.blocked_origins.insert(origin.to_string());
+        } else {
+            self.blocked_origins.remove(origin);
+        }
+    }
+
+    /// Start a request; a previous pending request is aborted
+    pub fn request(
+        &mut self,
+        origin: &str,
+        top_origin: Option<&str>,
+        user_activation: bool,
+    ) -> Result<u64, OtpError> {
+        let retriever = self.retriever.as_mut().ok_or(OtpError::NotSupported)?;
+        let blocked = self.blocked_origins.contains(origin)
+            || top_origin.is_some_and(|top| self.blocked_origins.contains(top));
+        if !user_activation || blocked {
+            return Err(OtpError::NotAllowed);
+        }
+
+        if let Some(previous) = self.pending.take() {
+            self.events.push(OtpEvent::Rejected { request_id: previous.id, error: OtpError::Aborted });
+        } else {
+            retriever.start();
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending = Some(OtpRequest {
+            id,
+            origin: origin.to_string(),
+            top_origin: top_origin.map(String::from),
+            started: Instant::now(),
+            code: None,
+        });
+        Ok(id)
+    }
+
+    /// Pending request, for rendering the consent prompt
+    pub fn pending(&self) -> Option<&OtpRequest> {
+        self.pending.as_ref()
+    }
+
+    /// Drain incoming SMS and expire the pending request
+    pub fn poll(&mut self) {
+        let (Some(retriever), Some(request)) = (self.retriever.as_mut(), self.pending.as_mut()) else {
+            return;
+        };
+        if request.code.is_none() {
+            while let Some(sms) = retriever.poll() {
+                if let Some(code) = OriginBoundCode::parse(&sms).filter(|c| request.matches(c)) {
+                    request.code = Some(code.code);
+                    break;
+                }
+            }
+        }
+        // The timeout does not apply while the user is looking at the prompt
+        if request.code.is_none() && request.started.elapsed() >= self.timeout {
+            self.finish(Err(OtpError::Timeout));
+        }
+    }
+
+    /// User allowed the code shown in the prompt
+    pub fn accept(&mut self) {
+        if let Some(code) = self.pending.as_ref().and_then(|r| r.code.clone()) {
+            self.finish(Ok(code));
+        }
+    }
+
+    /// User declined the prompt
+    pub fn decline(&mut self) {
+        if self.pending.as_ref().is_some_and(|r| r.code.is_some()) {
+            self.finish(Err(OtpError::Aborted));
+        }
+    }
+
+    /// AbortSignal fired
+    pub fn abort(&mut self, request_id: u64) {
+        if self.pending.as_ref().is_some_and(|r| r.id == request_id) {
+            self.finish(Err(OtpError::Aborted));
+        }
+    }
+
+    fn finish(&mut self, result: Result<String, OtpError>) {
+        let Some(request) = self.pending.take() else { return };
+        if let Some(retriever) = &mut self.retriever {
+            retriever.stop();
+        }
+        self.events.push(match result {
+            Ok(code) => OtpEvent::Resolved { request_id: request.id, code },
+            Err(error) => OtpEvent::Rejected { request_id: request.id, error },
+        });
+    }
+
+    pub fn take_events(&mut self) -> Vec<OtpEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Clone, Default)]
+    struct Inbox(Arc<Mutex<VecDeque<String>>>);
+
+    impl SmsRetriever for Inbox {
+        fn start(&mut self) {}
+        fn poll(&mut self) -> Option<String> { self.0.lock().unwrap().pop_front() }
+        fn stop(&mut self) {}
+    }
+
+    #[test]
+    fn test_parse_origin_bound_code() {
+        let code = OriginBoundCode::parse("Your code is 123456.\n\n@example.com #123456").unwrap();
+        assert_eq!(code.host, "example.com");
+        assert_eq!(code.code, "123456");
+        let code = OriginBoundCode::parse("@top.example #42 @pay.example\n").unwrap();
+        assert_eq!(code.embedded_host.as_deref(), Some("pay.example"));
+        assert!(OriginBoundCode::parse("Your code is 123456").is_none());
+        assert!(OriginBoundCode::parse("@example.com 123456").is_none());
+    }
+
+    #[test]
+    fn test_otp_flow() {
+        let inbox = Inbox::default();
+        let mut otp = OtpManager::new();
+        assert_eq!(otp.request("https://example.com", None, true), Err(OtpError::NotSupported));
+        otp.set_retriever(Box::new(inbox.clone()));
+        assert_eq!(otp.request("https://example.com", None, false), Err(OtpError::NotAllowed));
+
+        let first = otp.request("https://example.com", None, true).unwrap();
+        let id = otp.request("https://example.com", None, true).unwrap();
+        assert_eq!(otp.take_events(), vec![OtpEvent::Rejected { request_id: first, error: OtpError::Aborted }]);
+
+        inbox.0.lock().unwrap().push_back("@evil.example #111111".into());
+        inbox.0.lock().unwrap().push_back("Code: 222222\n@example.com #222222".into());
+        otp.poll();
+        assert_eq!(otp.pending().unwrap().code.as_deref(), Some("222222"));
+        otp.accept();
+        assert_eq!(otp.take_events(), vec![OtpEvent::Resolved { request_id: id, code: "222222".into() }]);
+
+        otp.timeout = Duration::ZERO;
+        let id = otp.request("https://pay.example", Some("https://shop.example"), true).unwrap();
+        otp.poll();
+        assert_eq!(otp.take_events(), vec![OtpEvent::Rejected { request_id: id, error: OtpError::Timeout }]);
+    }
+}
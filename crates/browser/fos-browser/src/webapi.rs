@@ -1,12 +1,17 @@
 //! Web API Integration
 //!
 //! Integrates fos-js web APIs: URL, Blob, File, FormData.
+//!
+//! Blob URLs are registered under the storage key of the document that
+//! created them and only resolve for documents with the same key.
 
 use fos_js::{
     JsUrl, JsUrlSearchParams, 
     Blob, FileReader,
     FormData,
 };
+use fos_security::StorageKey;
+use std::collections::HashMap;
 
 /// Web API manager for the browser
 pub struct WebApiManager {
@@ -14,6 +19,8 @@ pub struct WebApiManager {
     blobs: Vec<Blob>,
     /// Next blob ID
     next_blob_id: u32,
+    /// Live blob URLs with their storage key and blob ID
+    object_urls: HashMap<String, (StorageKey, u32)>,
 }
 
 impl WebApiManager {
@@ -22,6 +29,7 @@ impl WebApiManager {
         Self {
             blobs: Vec::new(),
             next_blob_id: 0,
+            object_urls: HashMap::new(),
         }
     }
     
@@ -52,20 +60,35 @@ impl WebApiManager {
         self.blobs.get(id as usize)
     }
     
-    /// Create a blob URL
-    pub fn create_object_url(&mut self, blob: Blob) -> String {
+    /// Body to upload blob `id` with, streamed from the stored bytes
+    pub fn blob_upload_body(&self, id: u32) -> Option<fos_net::UploadBody> {
+        let blob = self.get_blob(id)?;
+        Some(fos_net::UploadBody::new(fos_net::SharedBytes(blob.shared_bytes())))
+    }
+    
+    /// Create a blob URL for a document with storage key `key`
+    pub fn create_object_url(&mut self, blob: Blob, key: &StorageKey) -> String {
         let id = self.store_blob(blob);
-        format!("blob:{}", id)
+        let url = format!("blob:{}/{}", key.origin, id);
+        self.object_urls.insert(url.clone(), (key.clone(), id));
+        url
+    }
+    
+    /// Blob a blob URL refers to, if it was created in the same partition
+    pub fn resolve_object_url(&self, url: &str, key: &StorageKey) -> Option<&Blob> {
+        let (owner, id) = self.object_urls.get(strip_fragment(url))?;
+        if !owner.same_partition(key) {
+            return None;
+        }
+        self.get_blob(*id)
     }
     
-    /// Revoke a blob URL
-    pub fn revoke_object_url(&mut self, url: &str) {
-        if let Some(id_str) = url.strip_prefix("blob:") {
-            if let Ok(id) = id_str.parse::<usize>() {
-                if id < self.blobs.len() {
-                    // Mark as revoked (keep in place to preserve indices)
-                }
-            }
+    /// Revoke a blob URL; other partitions cannot revoke it
+    pub fn revoke_object_url(&mut self, url: &str, key: &StorageKey) {
+        let url = strip_fragment(url);
+        if self.object_urls.get(url).is_some_and(|(owner, _)| owner.same_partition(key)) {
+            // The blob stays in place to preserve indices
+            self.object_urls.remove(url);
         }
     }
     
@@ -103,6 +126,10 @@ impl WebApiManager {
     }
 }
 
+fn strip_fragment(url: &str) -> &str {
+    url.split('#').next().unwrap_or(url)
+}
+
 impl Default for WebApiManager {
     fn default() -> Self {
         Self::new()
@@ -150,7 +177,18 @@ mod tests {
             BlobOptions::default()
         );
         
-        let url = manager.create_object_url(blob);
-        assert!(url.starts_with("blob:"));
+        let key = StorageKey::first_party("https://a.com/page");
+        let url = manager.create_object_url(blob, &key);
+        assert!(url.starts_with("blob:https://a.com/"));
+        assert!(manager.resolve_object_url(&format!("{}#frag", url), &key).is_some());
+        
+        // The same origin embedded in another site does not see it
+        let embedded = StorageKey::new("https://b.com/", "https://a.com/widget");
+        assert!(manager.resolve_object_url(&url, &embedded).is_none());
+        manager.revoke_object_url(&url, &embedded);
+        assert!(manager.resolve_object_url(&url, &key).is_some());
+        
+        manager.revoke_object_url(&url, &key);
+        assert!(manager.resolve_object_url(&url, &key).is_none());
     }
 }
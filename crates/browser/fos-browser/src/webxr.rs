@@ -0,0 +1,913 @@
+//! WebXR Device API
+//!
+//! `navigator.xr` with inline sessions. Poses and input sources come from an
+//! [`XRDeviceBackend`]; without headset support the desktop emulator is
+//! used, so XR content can run inline in a canvas.
+//!
+//! Rendering goes through the page's own graphics context: an
+//! [`XRWebGLLayer`] targets a WebGL context, an [`XRGPULayer`] a WebGPU
+//! texture.
+
+use std::collections::HashMap;
+use std::f64::consts::FRAC_PI_2;
+
+use fos_canvas::webgl::{WebGLFramebuffer, WebGLRenderingContext};
+use fos_render::webgpu::{GPUDevice, GPUTextureDescriptor, GPUTextureFormat};
+
+/// Session mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum XRSessionMode {
+    Inline,
+    ImmersiveVr,
+    ImmersiveAr,
+}
+
+impl XRSessionMode {
+    pub fn is_immersive(&self) -> bool {
+        !matches!(self, Self::Inline)
+    }
+}
+
+/// Reference space type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum XRReferenceSpaceType {
+    Viewer,
+    Local,
+    LocalFloor,
+    BoundedFloor,
+    Unbounded,
+}
+
+impl XRReferenceSpaceType {
+    /// Feature descriptor string ("local-floor", ...)
+    pub fn feature_name(&self) -> &'static str {
+        match self {
+            Self::Viewer => "viewer",
+            Self::Local => "local",
+            Self::LocalFloor => "local-floor",
+            Self::BoundedFloor => "bounded-floor",
+            Self::Unbounded => "unbounded",
+        }
+    }
+}
+
+/// WebXR error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XRError {
+    /// Mode, feature or reference space not supported
+    NotSupported,
+    /// Session already ended or another immersive session is active
+    InvalidState,
+}
+
+impl std::fmt::Display for XRError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotSupported => write!(f, "The requested XR feature is not supported"),
+            Self::InvalidState => write!(f, "The XR session is in an invalid state"),
+        }
+    }
+}
+
+impl std::error::Error for XRError {}
+
+/// Position and orientation (unit quaternion x, y, z, w)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XRRigidTransform {
+    pub position: [f64; 3],
+    pub orientation: [f64; 4],
+}
+
+impl Default for XRRigidTransform {
+    fn default() -> Self {
+        Self { position: [0.0; 3], orientation: [0.0, 0.0, 0.0, 1.0] }
+    }
+}
+
+impl XRRigidTransform {
+    pub fn new(position: [f64; 3], orientation: [f64; 4]) -> Self {
+        let len = orientation.iter().map(|c| c * c).sum::<f64>().sqrt();
+        let orientation = if len > 0.0 {
+            orientation.map(|c| c / len)
+        } else {
+            [0.0, 0.0, 0.0, 1.0]
+        };
+        Self { position, orientation }
+    }
+
+    /// Pure translation
+    pub fn translation(x: f64, y: f64, z: f64) -> Self {
+        Self { position: [x, y, z], ..Default::default() }
+    }
+
+    /// Rotation about the vertical axis (radians, counter-clockwise seen from above)
+    pub fn yaw(angle: f64) -> Self {
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Self { position: [0.0; 3], orientation: [0.0, sin, 0.0, cos] }
+    }
+
+    /// Transform undoing this one
+    pub fn inverse(&self) -> Self {
+        let [x, y, z, w] = self.orientation;
+        let conjugate = [-x, -y, -z, w];
+        let p = rotate(conjugate, self.position);
+        Self { position: [-p[0], -p[1], -p[2]], orientation: conjugate }
+    }
+
+    /// `self` applied after `other` (`self * other`)
+    pub fn multiply(&self, other: &Self) -> Self {
+        let p = rotate(self.orientation, other.position);
+        Self::new(
+            [self.position[0] + p[0], self.position[1] + p[1], self.position[2] + p[2]],
+            quat_mul(self.orientation, other.orientation),
+        )
+    }
+
+    /// Apply to a point
+    pub fn transform_point(&self, point: [f64; 3]) -> [f64; 3] {
+        let p = rotate(self.orientation, point);
+        [p[0] + self.position[0], p[1] + self.position[1], p[2] + self.position[2]]
+    }
+
+    /// Column-major 4x4 matrix
+    pub fn matrix(&self) -> [f32; 16] {
+        let [x, y, z, w] = self.orientation;
+        let [px, py, pz] = self.position;
+        [
+            (1.0 - 2.0 * (y * y + z * z)) as f32, (2.0 * (x * y + z * w)) as f32, (2.0 * (x * z - y * w)) as f32, 0.0,
+            (2.0 * (x * y - z * w)) as f32, (1.0 - 2.0 * (x * x + z * z)) as f32, (2.0 * (y * z + x * w)) as f32, 0.0,
+            (2.0 * (x * z + y * w)) as f32, (2.0 * (y * z - x * w)) as f32, (1.0 - 2.0 * (x * x + y * y)) as f32, 0.0,
+            px as f32, py as f32, pz as f32, 1.0,
+        ]
+    }
+}
+
+fn quat_mul(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    let [ax, ay, az, aw] = a;
+    let [bx, by, bz, bw] = b;
+    [
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ]
+}
+
+fn rotate(q: [f64; 4], v: [f64; 3]) -> [f64; 3] {
+    let [x, y, z, _] = quat_mul(quat_mul(q, [v[0], v[1], v[2], 0.0]), [-q[0], -q[1], -q[2], q[3]]);
+    [x, y, z]
+}
+
+/// Column-major perspective projection
+fn perspective(fov_y: f64, aspect: f64, near: f64, far: f64) -> [f32; 16] {
+    let f = 1.0 / (fov_y / 2.0).tan();
+    let range = 1.0 / (near - far);
+    let mut m = [0.0f32; 16];
+    m[0] = (f / aspect.max(f64::EPSILON)) as f32;
+    m[5] = f as f32;
+    m[10] = ((far + near) * range) as f32;
+    m[11] = -1.0;
+    m[14] = (2.0 * far * near * range) as f32;
+    m
+}
+
+/// Eye a view renders for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XREye {
+    /// Monoscopic (inline) view
+    None,
+    Left,
+    Right,
+}
+
+/// One view of a viewer pose
+#[derive(Debug, Clone, PartialEq)]
+pub struct XRView {
+    pub eye: XREye,
+    pub projection_matrix: [f32; 16],
+    /// View position in the requested reference space
+    pub transform: XRRigidTransform,
+}
+
+/// Pose relative to a reference space
+#[derive(Debug, Clone, PartialEq)]
+pub struct XRPose {
+    pub transform: XRRigidTransform,
+    pub emulated_position: bool,
+}
+
+/// Viewer pose with one view per eye
+#[derive(Debug, Clone, PartialEq)]
+pub struct XRViewerPose {
+    pub transform: XRRigidTransform,
+    pub emulated_position: bool,
+    pub views: Vec<XRView>,
+}
+
+/// Input handedness
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XRHandedness {
+    None,
+    Left,
+    Right,
+}
+
+/// How an input source points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XRTargetRayMode {
+    Gaze,
+    TrackedPointer,
+    Screen,
+}
+
+/// Controller, hand or screen input
+#[derive(Debug, Clone, PartialEq)]
+pub struct XRInputSource {
+    pub handedness: XRHandedness,
+    pub target_ray_mode: XRTargetRayMode,
+    /// Input profile ids, most specific first
+    pub profiles: Vec<String>,
+    /// Target ray origin and direction (-z) in device local space
+    pub target_ray: XRRigidTransform,
+    /// Grip pose for tracked controllers
+    pub grip: Option<XRRigidTransform>,
+    /// Primary action ("select") is held
+    pub select_pressed: bool,
+}
+
+/// Reference space handed to content
+#[derive(Debug, Clone, PartialEq)]
+pub struct XRReferenceSpace {
+    pub space_type: XRReferenceSpaceType,
+    /// Offset applied with `getOffsetReferenceSpace`
+    pub origin_offset: XRRigidTransform,
+}
+
+impl XRReferenceSpace {
+    pub fn new(space_type: XRReferenceSpaceType) -> Self {
+        Self { space_type, origin_offset: XRRigidTransform::default() }
+    }
+
+    /// Space whose origin is moved by `offset`
+    pub fn get_offset_reference_space(&self, offset: &XRRigidTransform) -> Self {
+        Self { space_type: self.space_type, origin_offset: self.origin_offset.multiply(offset) }
+    }
+}
+
+/// XR hardware or an emulation of it
+pub trait XRDeviceBackend: Send + std::fmt::Debug {
+    fn name(&self) -> &str;
+
+    fn supports_mode(&self, mode: XRSessionMode) -> bool;
+
+    /// Reference spaces available in a mode beyond "viewer"
+    fn reference_spaces(&self, mode: XRSessionMode) -> Vec<XRReferenceSpaceType>;
+
+    /// Viewer pose in the device's local space at `time` (ms), if tracked
+    fn viewer_pose(&mut self, time: f64) -> Option<XRRigidTransform>;
+
+    /// Whether the viewer position is estimated rather than tracked
+    fn emulated_position(&self) -> bool { true }
+
+    /// Eye offsets from the viewer for immersive views
+    fn eye_offsets(&self) -> Vec<(XREye, XRRigidTransform)>;
+
+    /// Vertical field of view of immersive views (radians)
+    fn field_of_view(&self) -> f64 { FRAC_PI_2 }
+
+    /// Height of the local origin above the floor (meters)
+    fn floor_height(&self) -> f64 { 1.6 }
+
+    fn input_sources(&self) -> Vec<XRInputSource>;
+}
+
+/// Desktop emulator: the viewer is moved with keyboard and mouse, the mouse
+/// acts as a screen input source
+#[derive(Debug, Clone)]
+pub struct XREmulatorDevice {
+    pose: XRRigidTransform,
+    /// Allow immersive-vr sessions rendered as side-by-side stereo
+    pub immersive: bool,
+    /// Interpupillary distance (meters)
+    pub ipd: f64,
+    pub floor_height: f64,
+    pointer: XRInputSource,
+}
+
+impl Default for XREmulatorDevice {
+    fn default() -> Self {
+        Self {
+            pose: XRRigidTransform::default(),
+            immersive: false,
+            ipd: 0.063,
+            floor_height: 1.6,
+            pointer: XRInputSource {
+                handedness: XRHandedness::None,
+                target_ray_mode: XRTargetRayMode::Screen,
+                profiles: vec!["generic-touchscreen".into()],
+                target_ray: XRRigidTransform::default(),
+                grip: None,
+                select_pressed: false,
+            },
+        }
+    }
+}
+
+impl XREmulatorDevice {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn set_pose(&mut self, pose: XRRigidTransform) {
+        self.pose = pose;
+    }
+
+    /// Move along the viewing direction (W/S) and sideways (A/D)
+    pub fn walk(&mut self, forward: f64, right: f64) {
+        let step = rotate(self.pose.orientation, [right, 0.0, -forward]);
+        self.pose.position[0] += step[0];
+        self.pose.position[2] += step[2];
+    }
+
+    /// Turn the viewer (mouse drag)
+    pub fn turn(&mut self, yaw: f64) {
+        self.pose = XRRigidTransform { position: self.pose.position, ..XRRigidTransform::yaw(yaw) }
+            .multiply(&XRRigidTransform { position: [0.0; 3], ..self.pose });
+    }
+
+    /// Point the screen input along a ray from the viewer and set its select state
+    pub fn set_pointer(&mut self, direction: XRRigidTransform, pressed: bool) {
+        self.pointer.target_ray = self.pose.multiply(&direction);
+        self.pointer.select_pressed = pressed;
+    }
+}
+
+impl XRDeviceBackend for XREmulatorDevice {
+    fn name(&self) -> &str { "emulator" }
+
+    fn supports_mode(&self, mode: XRSessionMode) -> bool {
+        match mode {
+            XRSessionMode::Inline => true,
+            XRSessionMode::ImmersiveVr => self.immersive,
+            XRSessionMode::ImmersiveAr => false,
+        }
+    }
+
+    fn reference_spaces(&self, _mode: XRSessionMode) -> Vec<XRReferenceSpaceType> {
+        vec![XRReferenceSpaceType::Local, XRReferenceSpaceType::LocalFloor]
+    }
+
+    fn viewer_pose(&mut self, _time: f64) -> Option<XRRigidTransform> {
+        Some(self.pose)
+    }
+
+    fn eye_offsets(&self) -> Vec<(XREye, XRRigidTransform)> {
+        let half = self.ipd / 2.0;
+        vec![
+            (XREye::Left, XRRigidTransform::translation(-half, 0.0, 0.0)),
+            (XREye::Right, XRRigidTransform::translation(half, 0.0, 0.0)),
+        ]
+    }
+
+    fn floor_height(&self) -> f64 { self.floor_height }
+
+    fn input_sources(&self) -> Vec<XRInputSource> {
+        vec![self.pointer.clone()]
+    }
+}
+
+/// Region of a layer a view renders into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XRViewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Viewport of `eye` in a `width` x `height` target; stereo views are side by side
+fn eye_viewport(eye: XREye, width: u32, height: u32) -> XRViewport {
+    let (w, h) = (width as i32, height as i32);
+    match eye {
+        XREye::None => XRViewport { x: 0, y: 0, width: w, height: h },
+        XREye::Left => XRViewport { x: 0, y: 0, width: w / 2, height: h },
+        XREye::Right => XRViewport { x: w / 2, y: 0, width: w - w / 2, height: h },
+    }
+}
+
+/// Layer rendered with WebGL
+#[derive(Debug, Clone)]
+pub struct XRWebGLLayer {
+    /// `None` renders to the canvas's default framebuffer (inline sessions)
+    pub framebuffer: Option<WebGLFramebuffer>,
+    pub framebuffer_width: u32,
+    pub framebuffer_height: u32,
+    pub antialias: bool,
+}
+
+impl XRWebGLLayer {
+    /// Layer for a session drawing with `gl` into a `width` x `height` canvas
+    pub fn new(session: &XRSession, gl: &mut WebGLRenderingContext, width: u32, height: u32) -> Self {
+        let framebuffer = session.mode.is_immersive().then(|| {
+            let mut framebuffer = WebGLFramebuffer::new(session.id);
+            framebuffer.width = width;
+            framebuffer.height = height;
+            framebuffer.color_attachment = Some(session.id);
+            framebuffer
+        });
+        gl.viewport(0, 0, width as i32, height as i32);
+        Self { framebuffer, framebuffer_width: width, framebuffer_height: height, antialias: true }
+    }
+
+    pub fn get_viewport(&self, view: &XRView) -> XRViewport {
+        eye_viewport(view.eye, self.framebuffer_width, self.framebuffer_height)
+    }
+
+    /// Set the context's viewport for drawing `view`
+    pub fn bind_view(&self, gl: &mut WebGLRenderingContext, view: &XRView) {
+        let viewport = self.get_viewport(view);
+        gl.viewport(viewport.x, viewport.y, viewport.width, viewport.height);
+    }
+}
+
+/// Layer rendered with WebGPU into a color texture
+#[derive(Debug, Clone)]
+pub struct XRGPULayer {
+    /// Texture id on the device
+    pub texture: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl XRGPULayer {
+    pub fn new(device: &mut GPUDevice, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&GPUTextureDescriptor {
+            width,
+            height,
+            depth: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            format: GPUTextureFormat::Bgra8Unorm,
+        });
+        Self { texture, width, height }
+    }
+
+    pub fn get_viewport(&self, view: &XRView) -> XRViewport {
+        eye_viewport(view.eye, self.width, self.height)
+    }
+}
+
+/// Layer presented by a session
+#[derive(Debug, Clone)]
+pub enum XRLayer {
+    WebGL(XRWebGLLayer),
+    WebGPU(XRGPULayer),
+}
+
+impl XRLayer {
+    /// Size of the render target
+    pub fn size(&self) -> (u32, u32) {
+        match self {
+            Self::WebGL(layer) => (layer.framebuffer_width, layer.framebuffer_height),
+            Self::WebGPU(layer) => (layer.width, layer.height),
+        }
+    }
+
+    pub fn get_viewport(&self, view: &XRView) -> XRViewport {
+        match self {
+            Self::WebGL(layer) => layer.get_viewport(view),
+            Self::WebGPU(layer) => layer.get_viewport(view),
+        }
+    }
+}
+
+/// Session render state
+#[derive(Debug, Clone)]
+pub struct XRRenderState {
+    pub depth_near: f64,
+    pub depth_far: f64,
+    /// Vertical field of view of inline sessions (radians)
+    pub inline_vertical_field_of_view: Option<f64>,
+    pub base_layer: Option<XRLayer>,
+}
+
+/// Render state changes (`updateRenderState`)
+#[derive(Debug, Clone, Default)]
+pub struct XRRenderStateInit {
+    pub depth_near: Option<f64>,
+    pub depth_far: Option<f64>,
+    pub inline_vertical_field_of_view: Option<f64>,
+    pub base_layer: Option<XRLayer>,
+}
+
+/// Session visibility
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XRVisibilityState {
+    Visible,
+    VisibleBlurred,
+    Hidden,
+}
+
+/// Session event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XRSessionEvent {
+    /// Input source index started its primary action
+    SelectStart(usize),
+    /// Primary action completed
+    Select(usize),
+    SelectEnd(usize),
+    InputSourcesChange,
+    VisibilityChange(XRVisibilityState),
+    End,
+}
+
+/// Session request options
+#[derive(Debug, Clone, Default)]
+pub struct XRSessionInit {
+    pub required_features: Vec<String>,
+    pub optional_features: Vec<String>,
+}
+
+/// XR session
+#[derive(Debug)]
+pub struct XRSession {
+    pub id: u32,
+    pub mode: XRSessionMode,
+    pub render_state: XRRenderState,
+    pub visibility_state: XRVisibilityState,
+    /// Granted feature descriptors
+    pub enabled_features: Vec<String>,
+    ended: bool,
+    pending_callbacks: Vec<u32>,
+    next_callback: u32,
+    events: Vec<XRSessionEvent>,
+    /// Select state of each input source at the previous frame
+    select_state: Vec<bool>,
+}
+
+impl XRSession {
+    fn new(id: u32, mode: XRSessionMode, enabled_features: Vec<String>) -> Self {
+        Self {
+            id,
+            mode,
+            render_state: XRRenderState {
+                depth_near: 0.1,
+                depth_far: 1000.0,
+                inline_vertical_field_of_view: (mode == XRSessionMode::Inline).then_some(FRAC_PI_2),
+                base_layer: None,
+            },
+            visibility_state: XRVisibilityState::Visible,
+            enabled_features,
+            ended: false,
+            pending_callbacks: Vec::new(),
+            next_callback: 1,
+            events: Vec::new(),
+            select_state: Vec::new(),
+        }
+    }
+
+    pub fn is_ended(&self) -> bool { self.ended }
+
+    /// Apply render state changes; they take effect from the next frame
+    pub fn update_render_state(&mut self, init: XRRenderStateInit) -> Result<(), XRError> {
+        if self.ended {
+            return Err(XRError::InvalidState);
+        }
+        if init.inline_vertical_field_of_view.is_some() && self.mode.is_immersive() {
+            return Err(XRError::InvalidState);
+        }
+        let state = &mut self.render_state;
+        if let Some(near) = init.depth_near {
+            state.depth_near = near.max(0.0);
+        }
+        if let Some(far) = init.depth_far {
+            state.depth_far = far.max(state.depth_near);
+        }
+        if let Some(fov) = init.inline_vertical_field_of_view {
+            state.inline_vertical_field_of_view = Some(fov.clamp(0.0, std::f64::consts::PI));
+        }
+        if init.base_layer.is_some() {
+            state.base_layer = init.base_layer;
+        }
+        Ok(())
+    }
+
+    /// Queue a callback for the next XR frame, returning its handle
+    pub fn request_animation_frame(&mut self) -> u32 {
+        let handle = self.next_callback;
+        self.next_callback += 1;
+        if !self.ended {
+            self.pending_callbacks.push(handle);
+        }
+        handle
+    }
+
+    pub fn cancel_animation_frame(&mut self, handle: u32) {
+        self.pending_callbacks.retain(|&h| h != handle);
+    }
+
+    /// Take pending events
+    pub fn take_events(&mut self) -> Vec<XRSessionEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn end(&mut self) {
+        if !self.ended {
+            self.ended = true;
+            self.pending_callbacks.clear();
+            self.events.push(XRSessionEvent::End);
+        }
+    }
+
+    /// Projection for a view given the render state
+    fn projection(&self, eye: XREye, device_fov: f64) -> [f32; 16] {
+        let (width, height) = self.render_state.base_layer.as_ref().map(|l| l.size()).unwrap_or((1, 1));
+        let width = if eye == XREye::None { width } else { width / 2 };
+        let aspect = width.max(1) as f64 / height.max(1) as f64;
+        let fov = self.render_state.inline_vertical_field_of_view.unwrap_or(device_fov);
+        perspective(fov, aspect, self.render_state.depth_near, self.render_state.depth_far)
+    }
+}
+
+/// Snapshot of device state for one animation frame
+#[derive(Debug, Clone)]
+pub struct XRFrame {
+    pub session_id: u32,
+    /// Frame time (ms)
+    pub time: f64,
+    /// Viewer pose in device local space
+    viewer: Option<XRRigidTransform>,
+    emulated_position: bool,
+    /// (eye, offset from viewer, projection)
+    views: Vec<(XREye, XRRigidTransform, [f32; 16])>,
+    pub input_sources: Vec<XRInputSource>,
+    floor_height: f64,
+}
+
+impl XRFrame {
+    /// Origin of a reference space in device local space
+    fn space_origin(&self, space: &XRReferenceSpace) -> Option<XRRigidTransform> {
+        let base = match space.space_type {
+            XRReferenceSpaceType::Viewer => self.viewer?,
+            XRReferenceSpaceType::Local => XRRigidTransform::default(),
+            XRReferenceSpaceType::LocalFloor
+            | XRReferenceSpaceType::BoundedFloor
+            | XRReferenceSpaceType::Unbounded => XRRigidTransform::translation(0.0, -self.floor_height, 0.0),
+        };
+        Some(base.multiply(&space.origin_offset))
+    }
+
+    /// Viewer pose and views relative to `space`
+    pub fn get_viewer_pose(&self, space: &XRReferenceSpace) -> Option<XRViewerPose> {
+        let viewer = self.viewer?;
+        let transform = self.space_origin(space)?.inverse().multiply(&viewer);
+        let views = self.views.iter().map(|(eye, offset, projection)| XRView {
+            eye: *eye,
+            projection_matrix: *projection,
+            transform: transform.multiply(offset),
+        }).collect();
+        Some(XRViewerPose { transform, emulated_position: self.emulated_position, views })
+    }
+
+    /// Pose of an input source's target ray (or grip) relative to `space`
+    pub fn get_input_pose(&self, index: usize, grip: bool, space: &XRReferenceSpace) -> Option<XRPose> {
+        let source = self.input_sources.get(index)?;
+        let pose = if grip { source.grip? } else { source.target_ray };
+        Some(XRPose {
+            transform: self.space_origin(space)?.inverse().multiply(&pose),
+            emulated_position: self.emulated_position,
+        })
+    }
+}
+
+/// `navigator.xr`: owns the device and its sessions
+#[derive(Debug)]
+pub struct XRSystem {
+    device: Box<dyn XRDeviceBackend>,
+    sessions: HashMap<u32, XRSession>,
+    next_id: u32,
+}
+
+impl Default for XRSystem {
+    fn default() -> Self { Self::new() }
+}
+
+impl XRSystem {
+    /// System backed by the desktop emulator
+    pub fn new() -> Self {
+        Self::with_device(Box::new(XREmulatorDevice::new()))
+    }
+
+    pub fn with_device(device: Box<dyn XRDeviceBackend>) -> Self {
+        Self { device, sessions: HashMap::new(), next_id: 1 }
+    }
+
+    pub fn device_name(&self) -> &str { self.device.name() }
+
+    /// `isSessionSupported`
+    pub fn is_session_supported(&self, mode: XRSessionMode) -> bool {
+        self.device.supports_mode(mode)
+    }
+
+    /// Feature descriptors a mode can grant
+    fn supported_features(&self, mode: XRSessionMode) -> Vec<&'static str> {
+        let mut features = vec![XRReferenceSpaceType::Viewer.feature_name()];
+        features.extend(self.device.reference_spaces(mode).iter().map(|s| s.feature_name()));
+        features
+    }
+
+    /// `requestSession`: returns the new session's id
+    pub fn request_session(&mut self, mode: XRSessionMode, init: XRSessionInit) -> Result<u32, XRError> {
+        if !self.device.supports_mode(mode) {
+            return Err(XRError::NotSupported);
+        }
+        if mode.is_immersive() && self.sessions.values().any(|s| s.mode.is_immersive() && !s.ended) {
+            return Err(XRError::InvalidState);
+        }
+
+        let supported = self.supported_features(mode);
+        if init.required_features.iter().any(|f| !supported.contains(&f.as_str())) {
+            return Err(XRError::NotSupported);
+        }
+        // Viewer is always granted, immersive sessions get local by default
+        let mut enabled = vec![XRReferenceSpaceType::Viewer.feature_name().to_string()];
+        if mode.is_immersive() {
+            enabled.push(XRReferenceSpaceType::Local.feature_name().to_string());
+        }
+        for feature in init.required_features.iter().chain(&init.optional_features) {
+            if supported.contains(&feature.as_str()) && !enabled.contains(feature) {
+                enabled.push(feature.clone());
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.sessions.insert(id, XRSession::new(id, mode, enabled));
+        Ok(id)
+    }
+
+    pub fn session(&self, id: u32) -> Option<&XRSession> {
+        self.sessions.get(&id)
+    }
+
+    pub fn session_mut(&mut self, id: u32) -> Option<&mut XRSession> {
+        self.sessions.get_mut(&id)
+    }
+
+    /// `requestReferenceSpace`
+    pub fn request_reference_space(&self, session_id: u32, space_type: XRReferenceSpaceType) -> Result<XRReferenceSpace, XRError> {
+        let session = self.sessions.get(&session_id).ok_or(XRError::InvalidState)?;
+        if session.ended {
+            return Err(XRError::InvalidState);
+        }
+        if !session.enabled_features.iter().any(|f| f == space_type.feature_name()) {
+            return Err(XRError::NotSupported);
+        }
+        Ok(XRReferenceSpace::new(space_type))
+    }
+
+    /// Run one XR animation frame for a session: returns the callbacks to
+    /// invoke and the frame to pass them, or `None` if nothing was requested
+    pub fn run_animation_frame(&mut self, session_id: u32, time: f64) -> Option<(Vec<u32>, XRFrame)> {
+        let viewer = self.device.viewer_pose(time);
+        let input_sources = self.device.input_sources();
+        let eyes = self.device.eye_offsets();
+        let device_fov = self.device.field_of_view();
+        let emulated_position = self.device.emulated_position();
+        let floor_height = self.device.floor_height();
+
+        let session = self.sessions.get_mut(&session_id)?;
+        if session.ended || session.pending_callbacks.is_empty() {
+            return None;
+        }
+        let callbacks = std::mem::take(&mut session.pending_callbacks);
+
+        // Select events from changes in the primary action
+        if session.select_state.len() != input_sources.len() {
+            if !session.select_state.is_empty() || !input_sources.is_empty() {
+                session.events.push(XRSessionEvent::InputSourcesChange);
+            }
+            session.select_state = vec![false; input_sources.len()];
+        }
+        for (index, source) in input_sources.iter().enumerate() {
+            match (session.select_state[index], source.select_pressed) {
+                (false, true) => session.events.push(XRSessionEvent::SelectStart(index)),
+                (true, false) => {
+                    session.events.push(XRSessionEvent::Select(index));
+                    session.events.push(XRSessionEvent::SelectEnd(index));
+                }
+                _ => {}
+            }
+            session.select_state[index] = source.select_pressed;
+        }
+
+        let eyes = if session.mode.is_immersive() { eyes } else { vec![(XREye::None, XRRigidTransform::default())] };
+        let views = eyes.into_iter()
+            .map(|(eye, offset)| (eye, offset, session.projection(eye, device_fov)))
+            .collect();
+
+        Some((callbacks, XRFrame { session_id, time, viewer, emulated_position, views, input_sources, floor_height }))
+    }
+
+    /// `session.end()`
+    pub fn end_session(&mut self, session_id: u32) {
+        if let Some(session) = self.sessions.get_mut(&session_id) {
+            session.end();
+        }
+    }
+
+    /// Drop ended sessions whose events were taken
+    pub fn cleanup(&mut self) {
+        self.sessions.retain(|_, s| !s.ended || !s.events.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_session() {
+        let mut xr = XRSystem::new();
+        assert!(xr.is_session_supported(XRSessionMode::Inline));
+        assert!(!xr.is_session_supported(XRSessionMode::ImmersiveVr));
+        assert_eq!(xr.request_session(XRSessionMode::ImmersiveVr, XRSessionInit::default()), Err(XRError::NotSupported));
+
+        let id = xr.request_session(XRSessionMode::Inline, XRSessionInit::default()).unwrap();
+        assert!(xr.request_reference_space(id, XRReferenceSpaceType::Viewer).is_ok());
+        assert_eq!(xr.request_reference_space(id, XRReferenceSpaceType::Local), Err(XRError::NotSupported));
+
+        let mut gl = WebGLRenderingContext::new(640, 480);
+        let layer = XRWebGLLayer::new(xr.session(id).unwrap(), &mut gl, 640, 480);
+        assert!(layer.framebuffer.is_none());
+        let session = xr.session_mut(id).unwrap();
+        session.update_render_state(XRRenderStateInit { base_layer: Some(XRLayer::WebGL(layer)), ..Default::default() }).unwrap();
+
+        assert!(xr.run_animation_frame(id, 0.0).is_none());
+        let handle = xr.session_mut(id).unwrap().request_animation_frame();
+        let (callbacks, frame) = xr.run_animation_frame(id, 16.0).unwrap();
+        assert_eq!(callbacks, vec![handle]);
+
+        let space = xr.request_reference_space(id, XRReferenceSpaceType::Viewer).unwrap();
+        let pose = frame.get_viewer_pose(&space).unwrap();
+        assert_eq!(pose.views.len(), 1);
+        assert_eq!(pose.views[0].eye, XREye::None);
+        assert_eq!(pose.transform, XRRigidTransform::default());
+        let viewport = xr.session(id).unwrap().render_state.base_layer.as_ref().unwrap().get_viewport(&pose.views[0]);
+        assert_eq!(viewport, XRViewport { x: 0, y: 0, width: 640, height: 480 });
+
+        xr.end_session(id);
+        assert_eq!(xr.session_mut(id).unwrap().take_events().pop(), Some(XRSessionEvent::End));
+        xr.cleanup();
+        assert!(xr.session(id).is_none());
+    }
+
+    #[test]
+    fn test_local_floor_pose() {
+        let mut device = XREmulatorDevice::new();
+        device.set_pose(XRRigidTransform::translation(0.0, 0.2, 0.0));
+        device.turn(FRAC_PI_2);
+        device.walk(1.0, 0.0);
+        let mut xr = XRSystem::with_device(Box::new(device));
+        let init = XRSessionInit { optional_features: vec!["local-floor".into()], ..Default::default() };
+        let id = xr.request_session(XRSessionMode::Inline, init).unwrap();
+        let space = xr.request_reference_space(id, XRReferenceSpaceType::LocalFloor).unwrap();
+
+        xr.session_mut(id).unwrap().request_animation_frame();
+        let (_, frame) = xr.run_animation_frame(id, 0.0).unwrap();
+        let pose = frame.get_viewer_pose(&space).unwrap();
+        // Turned left, then walked one meter towards -x
+        let [x, y, z] = pose.transform.position;
+        assert!((x + 1.0).abs() < 1e-9 && (y - 1.8).abs() < 1e-9 && z.abs() < 1e-9);
+
+        let moved = space.get_offset_reference_space(&XRRigidTransform::translation(-1.0, 0.0, 0.0));
+        assert!(frame.get_viewer_pose(&moved).unwrap().transform.position[0].abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_immersive_stereo_and_select() {
+        let mut device = XREmulatorDevice::new();
+        device.immersive = true;
+        device.set_pointer(XRRigidTransform::default(), true);
+        let mut xr = XRSystem::with_device(Box::new(device));
+        let id = xr.request_session(XRSessionMode::ImmersiveVr, XRSessionInit::default()).unwrap();
+        assert_eq!(xr.request_session(XRSessionMode::ImmersiveVr, XRSessionInit::default()), Err(XRError::InvalidState));
+
+        let mut gpu = GPUDevice::new();
+        let layer = XRLayer::WebGPU(XRGPULayer::new(&mut gpu, 2000, 1000));
+        xr.session_mut(id).unwrap()
+            .update_render_state(XRRenderStateInit { base_layer: Some(layer), ..Default::default() })
+            .unwrap();
+        xr.session_mut(id).unwrap().request_animation_frame();
+        let (_, frame) = xr.run_animation_frame(id, 0.0).unwrap();
+
+        let space = xr.request_reference_space(id, XRReferenceSpaceType::Local).unwrap();
+        let pose = frame.get_viewer_pose(&space).unwrap();
+        assert_eq!(pose.views.len(), 2);
+        let layer = xr.session(id).unwrap().render_state.base_layer.clone().unwrap();
+        assert_eq!(layer.get_viewport(&pose.views[1]).x, 1000);
+        assert!(pose.views[0].transform.position[0] < 0.0);
+        assert!(frame.get_input_pose(0, false, &space).is_some());
+
+        let events = xr.session_mut(id).unwrap().take_events();
+        assert_eq!(events, vec![XRSessionEvent::InputSourcesChange, XRSessionEvent::SelectStart(0)]);
+    }
+}
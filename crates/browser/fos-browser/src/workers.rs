@@ -1,12 +1,21 @@
 //! Worker Integration
 //!
 //! Integrates fos-js web workers: Web Workers, Service Workers, Shared Workers.
+//!
+//! A shared worker is shared by documents with the same storage key only, so
+//! a third party embedded on two sites runs two separate instances.
 
 
 // Re-export worker types from fos-js worker module
 // Note: fos-js worker module is not fully exported at root level
 // We provide a simplified integration here
 
+use std::collections::HashMap;
+
+use fos_security::{StorageKey, WorkerKind, WorkerPolicyError};
+
+use crate::security::SecurityManager;
+
 /// Worker manager for the browser
 #[derive(Default)]
 pub struct WorkerIntegration {
@@ -16,6 +25,8 @@ pub struct WorkerIntegration {
     next_id: u32,
     /// Service worker registrations
     service_workers: Vec<ServiceWorkerReg>,
+    /// Running shared workers
+    shared_workers: Vec<SharedWorkerReg>,
 }
 
 /// Browser web worker
@@ -39,6 +50,17 @@ pub struct ServiceWorkerReg {
     pub state: ServiceWorkerState,
 }
 
+/// Running shared worker and the storage key it belongs to
+#[derive(Debug, Clone)]
+pub struct SharedWorkerReg {
+    pub id: u32,
+    pub key: StorageKey,
+    pub script_url: String,
+    pub name: String,
+    /// Connected documents
+    pub clients: usize,
+}
+
 /// Service worker state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ServiceWorkerState {
@@ -75,6 +97,22 @@ impl WorkerIntegration {
         id
     }
     
+    /// Create a web worker from a fetched script, checking it against the
+    /// page's `worker-src` and giving it a policy container built from the
+    /// script's response headers
+    pub fn create_worker_with_policy(
+        &mut self,
+        security: &mut SecurityManager,
+        url: &str,
+        origin: &str,
+        script: &str,
+        headers: &HashMap<String, String>,
+    ) -> Result<u32, WorkerPolicyError> {
+        security.check_worker_script(None, WorkerKind::Dedicated, url, origin)?;
+        security.attach_worker(None, WorkerKind::Dedicated, self.next_id as u64, url, headers)?;
+        Ok(self.create_worker(url, script))
+    }
+    
     /// Post message to worker
     pub fn post_message(&mut self, worker_id: u32, data: &str) {
         if let Some(worker) = self.workers.iter_mut().find(|w| w.id == worker_id) {
@@ -147,6 +185,45 @@ impl WorkerIntegration {
         }
     }
     
+    // === Shared Workers ===
+    
+    /// Connect a document with storage key `key` to the shared worker for
+    /// `script_url` and `name`, starting one if its partition has none
+    pub fn connect_shared_worker(&mut self, key: &StorageKey, script_url: &str, name: &str) -> u32 {
+        if let Some(worker) = self.shared_workers.iter_mut()
+            .find(|w| w.key.same_partition(key) && w.script_url == script_url && w.name == name)
+        {
+            worker.clients += 1;
+            return worker.id;
+        }
+        
+        let id = self.next_id;
+        self.next_id += 1;
+        self.shared_workers.push(SharedWorkerReg {
+            id,
+            key: key.clone(),
+            script_url: script_url.to_string(),
+            name: name.to_string(),
+            clients: 1,
+        });
+        
+        log::debug!("Started shared worker {} from {} for {}", id, script_url, key.top_level_site);
+        id
+    }
+    
+    /// Disconnect one document; the worker stops with its last client
+    pub fn disconnect_shared_worker(&mut self, worker_id: u32) {
+        if let Some(worker) = self.shared_workers.iter_mut().find(|w| w.id == worker_id) {
+            worker.clients = worker.clients.saturating_sub(1);
+        }
+        self.shared_workers.retain(|w| w.clients > 0);
+    }
+    
+    /// Get running shared worker count
+    pub fn active_shared_workers(&self) -> usize {
+        self.shared_workers.len()
+    }
+    
     /// Cleanup terminated workers
     pub fn cleanup(&mut self) {
         self.workers.retain(|w| !w.terminated);
@@ -158,6 +235,7 @@ impl WorkerIntegration {
         WorkerStats {
             web_workers: self.active_workers(),
             service_workers: self.service_workers.len(),
+            shared_workers: self.shared_workers.len(),
             terminated: self.workers.iter().filter(|w| w.terminated).count(),
         }
     }
@@ -168,6 +246,7 @@ impl WorkerIntegration {
 pub struct WorkerStats {
     pub web_workers: usize,
     pub service_workers: usize,
+    pub shared_workers: usize,
     pub terminated: usize,
 }
 
@@ -203,6 +282,36 @@ mod tests {
         assert_eq!(workers.active_workers(), 0);
     }
     
+    #[test]
+    fn test_worker_policy() {
+        let mut security = SecurityManager::new();
+        security.parse_csp("worker-src 'self'");
+        let mut workers = WorkerIntegration::new();
+        let origin = "https://example.com";
+        
+        assert!(workers.create_worker_with_policy(&mut security, "https://cdn.com/w.js", origin, "", &HashMap::new()).is_err());
+        assert_eq!(workers.active_workers(), 0);
+        let id = workers.create_worker_with_policy(&mut security, "https://example.com/w.js", origin, "", &HashMap::new()).unwrap();
+        assert!(security.worker_policy(WorkerKind::Dedicated, id as u64).is_some());
+    }
+    
+    #[test]
+    fn test_shared_workers_partitioned() {
+        let mut workers = WorkerIntegration::new();
+        let on_a = StorageKey::new("https://a.com/", "https://tracker.net/frame");
+        let on_b = StorageKey::new("https://b.com/", "https://tracker.net/frame");
+        
+        let first = workers.connect_shared_worker(&on_a, "https://tracker.net/sw.js", "sync");
+        assert_eq!(workers.connect_shared_worker(&on_a, "https://tracker.net/sw.js", "sync"), first);
+        let other = workers.connect_shared_worker(&on_b, "https://tracker.net/sw.js", "sync");
+        assert_ne!(other, first);
+        assert_eq!(workers.active_shared_workers(), 2);
+        
+        workers.disconnect_shared_worker(first);
+        workers.disconnect_shared_worker(first);
+        assert_eq!(workers.active_shared_workers(), 1);
+    }
+    
     #[test]
     fn test_service_worker_registration() {
         let mut workers = WorkerIntegration::new();
@@ -0,0 +1,161 @@
+//! Page Zoom
+//!
+//! Full-page zoom makes each CSS pixel cover more device pixels, so pages
+//! lay out in a narrower viewport and everything grows; text-only zoom
+//! grows font sizes alone. Full-page zoom is remembered per origin and
+//! applies to every tab showing it, while text zoom belongs to the tab.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use fos_dom::url::Url;
+
+/// Zoom factors Ctrl+Plus and Ctrl+Minus step through
+pub const ZOOM_LEVELS: [f64; 17] = [
+    0.25, 0.33, 0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0, 4.0, 5.0,
+];
+
+/// A zoom command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoomStep {
+    In,
+    Out,
+    Reset,
+}
+
+impl ZoomStep {
+    /// Factor after this step from `factor`
+    pub fn apply(self, factor: f64) -> f64 {
+        // Factors between levels step to the nearest level past them
+        const EPSILON: f64 = 0.001;
+        match self {
+            Self::In => ZOOM_LEVELS.iter().copied()
+                .find(|&level| level > factor + EPSILON)
+                .unwrap_or(ZOOM_LEVELS[ZOOM_LEVELS.len() - 1]),
+            Self::Out => ZOOM_LEVELS.iter().rev().copied()
+                .find(|&level| level < factor - EPSILON)
+                .unwrap_or(ZOOM_LEVELS[0]),
+            Self::Reset => 1.0,
+        }
+    }
+}
+
+/// Full-page zoom per origin, saved to disk
+#[derive(Debug, Default)]
+pub struct ZoomStore {
+    /// Zoom factor by origin; origins at 100% are left out
+    levels: HashMap<String, f64>,
+    storage_path: Option<PathBuf>,
+}
+
+impl ZoomStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Create with persistence
+    pub fn with_storage(path: PathBuf) -> Self {
+        let mut store = Self::new();
+        store.storage_path = Some(path);
+        store.load();
+        store
+    }
+    
+    /// Zoom factor for pages at `url`
+    pub fn get(&self, url: &str) -> f64 {
+        origin(url)
+            .and_then(|origin| self.levels.get(&origin).copied())
+            .unwrap_or(1.0)
+    }
+    
+    /// Zoom pages of `url`'s origin by `factor` from now on
+    pub fn set(&mut self, url: &str, factor: f64) {
+        let Some(origin) = origin(url) else { return };
+        if (factor - 1.0).abs() < f64::EPSILON {
+            self.levels.remove(&origin);
+        } else {
+            self.levels.insert(origin, factor);
+        }
+        self.save();
+    }
+    
+    /// Forget the zoom of every origin
+    pub fn clear(&mut self) {
+        self.levels.clear();
+        self.save();
+    }
+    
+    /// Save to disk
+    pub fn save(&self) {
+        let Some(path) = &self.storage_path else { return };
+        
+        let mut data = String::new();
+        for (origin, factor) in &self.levels {
+            data.push_str(&format!("{}\t{}\n", origin, factor));
+        }
+        
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = fs::write(path, data) {
+            log::warn!("Failed to save zoom levels to {}: {}", path.display(), e);
+        }
+    }
+    
+    /// Load from disk
+    pub fn load(&mut self) {
+        let Some(path) = &self.storage_path else { return };
+        
+        let data = match fs::read_to_string(path) {
+            Ok(d) => d,
+            Err(_) => return,
+        };
+        
+        for line in data.lines() {
+            if let Some((origin, factor)) = line.split_once('\t') {
+                if let Ok(factor) = factor.parse() {
+                    self.levels.insert(origin.to_string(), factor);
+                }
+            }
+        }
+    }
+}
+
+/// Origin zoom is kept for; pages without a host share their scheme's
+fn origin(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    Some(url.origin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_zoom_steps() {
+        assert_eq!(ZoomStep::In.apply(1.0), 1.1);
+        assert_eq!(ZoomStep::Out.apply(1.0), 0.9);
+        assert_eq!(ZoomStep::In.apply(1.2), 1.25);
+        assert_eq!(ZoomStep::Out.apply(1.2), 1.1);
+        assert_eq!(ZoomStep::In.apply(5.0), 5.0);
+        assert_eq!(ZoomStep::Out.apply(0.25), 0.25);
+        assert_eq!(ZoomStep::Reset.apply(3.0), 1.0);
+    }
+    
+    #[test]
+    fn test_zoom_persists_per_origin() {
+        let path = std::env::temp_dir().join(format!("fos-zoom-test-{}", std::process::id()));
+        let mut store = ZoomStore::with_storage(path.clone());
+        store.set("https://example.com/a", 1.5);
+        assert_eq!(store.get("https://example.com/b?c"), 1.5);
+        assert_eq!(store.get("http://example.com/"), 1.0);
+        
+        let loaded = ZoomStore::with_storage(path.clone());
+        assert_eq!(loaded.get("https://example.com/"), 1.5);
+        
+        store.set("https://example.com/", 1.0);
+        assert_eq!(ZoomStore::with_storage(path.clone()).get("https://example.com/"), 1.0);
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,233 @@
+//! Path Boolean Operations
+//!
+//! Union, intersection, difference and xor of filled polygons, and
+//! simplification of self-intersecting polygons into non-overlapping ones.
+//! The plane is cut into horizontal bands at every vertex and edge crossing;
+//! no edges cross inside a band, so each band splits into trapezoids whose
+//! insideness follows from the winding numbers of both operands.
+
+use crate::raster::FillRule;
+
+/// Non-overlapping polygons, filling the same under either fill rule
+pub type Region = Vec<Vec<(f64, f64)>>;
+
+/// Boolean operation on two filled areas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathOp {
+    Union,
+    Intersect,
+    Difference,
+    Xor,
+}
+
+impl PathOp {
+    fn apply(self, a: bool, b: bool) -> bool {
+        match self {
+            Self::Union => a || b,
+            Self::Intersect => a && b,
+            Self::Difference => a && !b,
+            Self::Xor => a != b,
+        }
+    }
+}
+
+/// Polygon edge with `y0 < y1`, from operand `source`
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+    dir: i32,
+    source: usize,
+}
+
+impl Edge {
+    fn x_at(&self, y: f64) -> f64 {
+        self.x0 + (y - self.y0) * (self.x1 - self.x0) / (self.y1 - self.y0)
+    }
+}
+
+/// Trapezoid growing downwards between two edges
+#[derive(Debug, Clone, Copy)]
+struct Trapezoid {
+    left: usize,
+    right: usize,
+    top: f64,
+}
+
+impl Trapezoid {
+    /// Clockwise outline (in y-down space), so results fill under either rule
+    fn polygon(&self, edges: &[Edge], bottom: f64) -> Vec<(f64, f64)> {
+        let (l, r) = (&edges[self.left], &edges[self.right]);
+        vec![
+            (l.x_at(self.top), self.top),
+            (r.x_at(self.top), self.top),
+            (r.x_at(bottom), bottom),
+            (l.x_at(bottom), bottom),
+        ]
+    }
+}
+
+fn collect(polygons: &[Vec<(f64, f64)>], source: usize, edges: &mut Vec<Edge>) {
+    for polygon in polygons {
+        for (i, &(xa, ya)) in polygon.iter().enumerate() {
+            let (xb, yb) = polygon[(i + 1) % polygon.len()];
+            if ya == yb || !(xa.is_finite() && ya.is_finite() && xb.is_finite() && yb.is_finite()) {
+                continue;
+            }
+            edges.push(if ya < yb {
+                Edge { x0: xa, y0: ya, x1: xb, y1: yb, dir: 1, source }
+            } else {
+                Edge { x0: xb, y0: yb, x1: xa, y1: ya, dir: -1, source }
+            });
+        }
+    }
+}
+
+/// Height at which two edges cross strictly between their shared extent
+fn crossing(a: &Edge, b: &Edge) -> Option<f64> {
+    let (top, bottom) = (a.y0.max(b.y0), a.y1.min(b.y1));
+    if top >= bottom {
+        return None;
+    }
+    let d0 = a.x_at(top) - b.x_at(top);
+    let d1 = a.x_at(bottom) - b.x_at(bottom);
+    if d0 * d1 >= 0.0 {
+        return None;
+    }
+    Some(top + (bottom - top) * d0 / (d0 - d1))
+}
+
+/// Non-overlapping polygons covering the area the polygons fill under `rule`
+pub fn simplify(polygons: &[Vec<(f64, f64)>], rule: FillRule) -> Region {
+    let mut edges = Vec::new();
+    collect(polygons, 0, &mut edges);
+    sweep(&edges, |winding| rule.inside(winding[0]))
+}
+
+/// Non-overlapping polygons covering `a op b`
+pub fn op(
+    a: &[Vec<(f64, f64)>],
+    a_rule: FillRule,
+    b: &[Vec<(f64, f64)>],
+    b_rule: FillRule,
+    op: PathOp,
+) -> Region {
+    let mut edges = Vec::new();
+    collect(a, 0, &mut edges);
+    collect(b, 1, &mut edges);
+    sweep(&edges, |winding| op.apply(a_rule.inside(winding[0]), b_rule.inside(winding[1])))
+}
+
+fn sweep(edges: &[Edge], inside: impl Fn([i32; 2]) -> bool) -> Region {
+    let mut ys: Vec<f64> = edges.iter().flat_map(|e| [e.y0, e.y1]).collect();
+    for (i, a) in edges.iter().enumerate() {
+        ys.extend(edges[i + 1..].iter().filter_map(|b| crossing(a, b)));
+    }
+    ys.sort_by(f64::total_cmp);
+    ys.dedup();
+
+    let mut done = Vec::new();
+    let mut open: Vec<Trapezoid> = Vec::new();
+    let mut active: Vec<(f64, usize)> = Vec::new();
+    for band in ys.windows(2) {
+        let (top, bottom) = (band[0], band[1]);
+        let mid = (top + bottom) / 2.0;
+        active.clear();
+        active.extend(
+            edges
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| e.y0 <= mid && mid < e.y1)
+                .map(|(i, e)| (e.x_at(mid), i)),
+        );
+        active.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // Spans between the edges where the result switches in and out
+        let mut winding = [0, 0];
+        let mut start = None;
+        let mut next = Vec::new();
+        for &(x, i) in &active {
+            let was = inside(winding);
+            winding[edges[i].source] += edges[i].dir;
+            let now = inside(winding);
+            if !was && now {
+                start = Some((x, i));
+            } else if was && !now {
+                // Coincident edges leave empty spans
+                let Some((left_x, left)) = start.take() else { continue };
+                if x - left_x <= 1e-9 {
+                    continue;
+                }
+                // Trapezoids between the same two edges keep growing
+                match open.iter().position(|t| t.left == left && t.right == i) {
+                    Some(pos) => next.push(open.swap_remove(pos)),
+                    None => next.push(Trapezoid { left, right: i, top }),
+                }
+            }
+        }
+        done.extend(open.drain(..).map(|t| t.polygon(edges, top)));
+        open = next;
+    }
+    if let Some(&bottom) = ys.last() {
+        done.extend(open.iter().map(|t| t.polygon(edges, bottom)));
+    }
+    done
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raster::contains;
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<(f64, f64)> {
+        vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size)]
+    }
+
+    fn area(polygons: &[Vec<(f64, f64)>]) -> f64 {
+        polygons
+            .iter()
+            .map(|p| {
+                let twice: f64 = (0..p.len())
+                    .map(|i| {
+                        let (a, b) = (p[i], p[(i + 1) % p.len()]);
+                        a.0 * b.1 - b.0 * a.1
+                    })
+                    .sum();
+                twice.abs() / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_ops() {
+        let a = [square(0.0, 0.0, 10.0)];
+        let b = [square(5.0, 5.0, 10.0)];
+        let area_of = |op| area(&super::op(&a, FillRule::NonZero, &b, FillRule::NonZero, op));
+        assert!((area_of(PathOp::Union) - 175.0).abs() < 1e-9);
+        assert!((area_of(PathOp::Intersect) - 25.0).abs() < 1e-9);
+        assert!((area_of(PathOp::Difference) - 75.0).abs() < 1e-9);
+        assert!((area_of(PathOp::Xor) - 150.0).abs() < 1e-9);
+
+        let diff = super::op(&a, FillRule::NonZero, &b, FillRule::NonZero, PathOp::Difference);
+        assert!(contains(&diff, 2.0, 8.0, FillRule::EvenOdd));
+        assert!(!contains(&diff, 7.0, 7.0, FillRule::NonZero));
+    }
+
+    #[test]
+    fn test_simplify_self_intersecting() {
+        // A pentagram: the center is wound twice
+        let star: Vec<(f64, f64)> = (0..5)
+            .map(|i| {
+                let angle = -std::f64::consts::FRAC_PI_2 + i as f64 * 4.0 * std::f64::consts::PI / 5.0;
+                (50.0 + 40.0 * angle.cos(), 50.0 + 40.0 * angle.sin())
+            })
+            .collect();
+        let nonzero = simplify(std::slice::from_ref(&star), FillRule::NonZero);
+        let evenodd = simplify(std::slice::from_ref(&star), FillRule::EvenOdd);
+        assert!(contains(&nonzero, 50.0, 50.0, FillRule::EvenOdd));
+        assert!(!contains(&evenodd, 50.0, 50.0, FillRule::NonZero));
+        assert!(area(&nonzero) > area(&evenodd));
+    }
+}
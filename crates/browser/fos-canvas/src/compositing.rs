@@ -104,33 +104,147 @@ impl CompositeOperation {
     }
 }
 
-/// Blend two colors
-pub fn blend_colors(src: (u8, u8, u8, u8), dst: (u8, u8, u8, u8), op: CompositeOperation) -> (u8, u8, u8, u8) {
-    let (sr, sg, sb, sa) = (src.0 as f64 / 255.0, src.1 as f64 / 255.0, src.2 as f64 / 255.0, src.3 as f64 / 255.0);
-    let (dr, dg, db, da) = (dst.0 as f64 / 255.0, dst.1 as f64 / 255.0, dst.2 as f64 / 255.0, dst.3 as f64 / 255.0);
+impl CompositeOperation {
+    /// Whether the operation also changes the destination where the source
+    /// is transparent (outside the drawn shape)
+    pub fn is_unbounded(&self) -> bool {
+        matches!(
+            self,
+            Self::SourceIn | Self::SourceOut | Self::DestinationIn | Self::DestinationAtop | Self::Copy
+        )
+    }
     
-    let (r, g, b, a) = match op {
-        CompositeOperation::SourceOver => {
-            let a = sa + da * (1.0 - sa);
-            if a < 1e-10 {
-                (0.0, 0.0, 0.0, 0.0)
+    /// Whether this is a blend mode (`multiply` through `luminosity`)
+    pub fn is_blend_mode(&self) -> bool {
+        !matches!(
+            self,
+            Self::SourceOver | Self::SourceIn | Self::SourceOut | Self::SourceAtop
+                | Self::DestinationOver | Self::DestinationIn | Self::DestinationOut
+                | Self::DestinationAtop | Self::Lighter | Self::Copy | Self::Xor
+        )
+    }
+}
+
+/// Composite a source pixel onto a destination pixel; both are
+/// non-premultiplied RGBA in 0..=1
+pub fn composite(src: [f64; 4], dst: [f64; 4], op: CompositeOperation) -> [f64; 4] {
+    use CompositeOperation as Op;
+    let (sa, da) = (src[3], dst[3]);
+    // Porter-Duff factors for source and destination
+    let (fa, fb) = match op {
+        Op::SourceIn => (da, 0.0),
+        Op::SourceOut => (1.0 - da, 0.0),
+        Op::SourceAtop => (da, 1.0 - sa),
+        Op::DestinationOver => (1.0 - da, 1.0),
+        Op::DestinationIn => (0.0, sa),
+        Op::DestinationOut => (0.0, 1.0 - sa),
+        Op::DestinationAtop => (1.0 - da, sa),
+        Op::Lighter => (1.0, 1.0),
+        Op::Copy => (1.0, 0.0),
+        Op::Xor => (1.0 - da, 1.0 - sa),
+        _ => (1.0, 1.0 - sa),
+    };
+    let mut source = [src[0], src[1], src[2]];
+    if op.is_blend_mode() {
+        // Mix the colors where both are present, then composite source-over
+        let blended = blend(op, [dst[0], dst[1], dst[2]], source);
+        for (c, b) in source.iter_mut().zip(blended) {
+            *c = (1.0 - da) * *c + da * b;
+        }
+    }
+    let alpha = (sa * fa + da * fb).min(1.0);
+    if alpha <= 1e-10 {
+        return [0.0; 4];
+    }
+    let mut out = [0.0, 0.0, 0.0, alpha];
+    for i in 0..3 {
+        out[i] = ((source[i] * sa * fa + dst[i] * da * fb) / alpha).clamp(0.0, 1.0);
+    }
+    out
+}
+
+/// Mix function `B(cb, cs)` of a blend mode
+fn blend(op: CompositeOperation, cb: [f64; 3], cs: [f64; 3]) -> [f64; 3] {
+    use CompositeOperation as Op;
+    let separable = |f: fn(f64, f64) -> f64| [f(cb[0], cs[0]), f(cb[1], cs[1]), f(cb[2], cs[2])];
+    match op {
+        Op::Multiply => separable(|b, s| b * s),
+        Op::Screen => separable(screen),
+        Op::Overlay => separable(|b, s| hard_light(s, b)),
+        Op::Darken => separable(f64::min),
+        Op::Lighten => separable(f64::max),
+        Op::ColorDodge => separable(|b, s| {
+            if b == 0.0 { 0.0 } else if s >= 1.0 { 1.0 } else { (b / (1.0 - s)).min(1.0) }
+        }),
+        Op::ColorBurn => separable(|b, s| {
+            if b >= 1.0 { 1.0 } else if s <= 0.0 { 0.0 } else { 1.0 - ((1.0 - b) / s).min(1.0) }
+        }),
+        Op::HardLight => separable(hard_light),
+        Op::SoftLight => separable(|b, s| {
+            if s <= 0.5 {
+                b - (1.0 - 2.0 * s) * b * (1.0 - b)
             } else {
-                let r = (sr * sa + dr * da * (1.0 - sa)) / a;
-                let g = (sg * sa + dg * da * (1.0 - sa)) / a;
-                let b = (sb * sa + db * da * (1.0 - sa)) / a;
-                (r, g, b, a)
+                let d = if b <= 0.25 { ((16.0 * b - 12.0) * b + 4.0) * b } else { b.sqrt() };
+                b + (2.0 * s - 1.0) * (d - b)
             }
+        }),
+        Op::Difference => separable(|b, s| (b - s).abs()),
+        Op::Exclusion => separable(|b, s| b + s - 2.0 * b * s),
+        Op::Hue => set_lum(set_sat(cs, sat(cb)), lum(cb)),
+        Op::Saturation => set_lum(set_sat(cb, sat(cs)), lum(cb)),
+        Op::Color => set_lum(cs, lum(cb)),
+        Op::Luminosity => set_lum(cb, lum(cs)),
+        _ => cs,
+    }
+}
+
+fn screen(b: f64, s: f64) -> f64 {
+    b + s - b * s
+}
+
+fn hard_light(b: f64, s: f64) -> f64 {
+    if s <= 0.5 { b * 2.0 * s } else { screen(b, 2.0 * s - 1.0) }
+}
+
+fn lum(c: [f64; 3]) -> f64 {
+    0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+fn set_lum(c: [f64; 3], l: f64) -> [f64; 3] {
+    let d = l - lum(c);
+    let c = [c[0] + d, c[1] + d, c[2] + d];
+    let l = lum(c);
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+    c.map(|v| {
+        if n < 0.0 {
+            l + (v - l) * l / (l - n)
+        } else if x > 1.0 {
+            l + (v - l) * (1.0 - l) / (x - l)
+        } else {
+            v
         }
-        CompositeOperation::Multiply => {
-            (sr * dr, sg * dg, sb * db, sa * da)
-        }
-        CompositeOperation::Screen => {
-            (1.0 - (1.0 - sr) * (1.0 - dr), 1.0 - (1.0 - sg) * (1.0 - dg), 1.0 - (1.0 - sb) * (1.0 - db), 1.0 - (1.0 - sa) * (1.0 - da))
-        }
-        _ => (sr, sg, sb, sa), // Default to source
-    };
-    
-    ((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8, (a * 255.0) as u8)
+    })
+}
+
+fn sat(c: [f64; 3]) -> f64 {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+fn set_sat(c: [f64; 3], s: f64) -> [f64; 3] {
+    let max = c[0].max(c[1]).max(c[2]);
+    let min = c[0].min(c[1]).min(c[2]);
+    if max <= min {
+        return [0.0; 3];
+    }
+    c.map(|v| (v - min) * s / (max - min))
+}
+
+/// Blend two colors
+pub fn blend_colors(src: (u8, u8, u8, u8), dst: (u8, u8, u8, u8), op: CompositeOperation) -> (u8, u8, u8, u8) {
+    let to_unit = |c: (u8, u8, u8, u8)| [c.0, c.1, c.2, c.3].map(|v| v as f64 / 255.0);
+    let [r, g, b, a] = composite(to_unit(src), to_unit(dst), op).map(|v| (v * 255.0).round() as u8);
+    (r, g, b, a)
 }
 
 #[cfg(test)]
@@ -142,4 +256,32 @@ mod tests {
         assert_eq!(CompositeOperation::from_str("source-over"), Some(CompositeOperation::SourceOver));
         assert_eq!(CompositeOperation::from_str("multiply"), Some(CompositeOperation::Multiply));
     }
+    
+    #[test]
+    fn test_porter_duff() {
+        let red = (255, 0, 0, 255);
+        let blue = (0, 0, 255, 255);
+        let clear = (0, 0, 0, 0);
+        assert_eq!(blend_colors(red, blue, CompositeOperation::SourceOver), red);
+        assert_eq!(blend_colors(red, blue, CompositeOperation::DestinationOver), blue);
+        assert_eq!(blend_colors(red, clear, CompositeOperation::SourceIn), clear);
+        assert_eq!(blend_colors(red, blue, CompositeOperation::DestinationOut), clear);
+        assert_eq!(blend_colors(red, blue, CompositeOperation::Xor), clear);
+        assert_eq!(blend_colors(red, blue, CompositeOperation::Lighter), (255, 0, 255, 255));
+        assert_eq!(blend_colors((255, 0, 0, 128), blue, CompositeOperation::Copy), (255, 0, 0, 128));
+    }
+    
+    #[test]
+    fn test_blend_modes() {
+        let gray = (128, 128, 128, 255);
+        let white = (255, 255, 255, 255);
+        assert_eq!(blend_colors(gray, white, CompositeOperation::Multiply), gray);
+        assert_eq!(blend_colors(gray, white, CompositeOperation::Screen), white);
+        assert_eq!(blend_colors(white, gray, CompositeOperation::Difference), (127, 127, 127, 255));
+        // Blending only applies where the destination is opaque
+        assert_eq!(blend_colors(gray, (0, 0, 0, 0), CompositeOperation::Multiply), gray);
+        // Non-separable: black's luminosity on red is black
+        let (r, g, b, _) = blend_colors((0, 0, 0, 255), (255, 0, 0, 255), CompositeOperation::Luminosity);
+        assert_eq!((r, g, b), (0, 0, 0));
+    }
 }
@@ -4,10 +4,17 @@
 //!
 //! Uses local SIMD utilities for accelerated pixel operations.
 
+use std::sync::Arc;
+
+use crate::boolean::{self, PathOp, Region};
 use crate::path::Path2D;
 use crate::transforms::TransformMatrix;
-use crate::compositing::{CompositeOperation, BlendMode};
+use crate::compositing::{CompositeOperation, BlendMode, composite};
+use crate::raster::{contains, FillRule, Mask};
 use crate::simd::{Color4, blend_color, blend_colors_4};
+use crate::stroke::{dash, outline, StrokeParams};
+use crate::text::{CanvasFont, CanvasFonts};
+use crate::CanvasError;
 
 /// Canvas 2D rendering context
 #[derive(Debug)]
@@ -22,6 +29,8 @@ pub struct CanvasRenderingContext2D {
     states: Vec<CanvasState>,
     /// Current path
     current_path: Path2D,
+    /// Fonts for text; system fonts unless set
+    fonts: Option<Arc<CanvasFonts>>,
 }
 
 /// Canvas state (for save/restore)
@@ -45,7 +54,12 @@ pub struct CanvasState {
     pub shadow_offset_y: f64,
     pub shadow_blur: f64,
     pub shadow_color: Color,
-    pub clip_path: Option<Path2D>,
+    pub image_smoothing_enabled: bool,
+    pub image_smoothing_quality: ImageSmoothingQuality,
+    /// Intersection of all clip regions, in canvas pixels
+    pub clip_mask: Option<Arc<Mask>>,
+    /// The same region as non-overlapping canvas-space polygons
+    pub clip_region: Option<Arc<Region>>,
 }
 
 /// Fill style
@@ -152,6 +166,88 @@ pub enum TextBaseline {
     Bottom,
 }
 
+/// Image smoothing quality
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageSmoothingQuality {
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+impl TextAlign {
+    /// Parse from string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "start" => Some(Self::Start),
+            "end" => Some(Self::End),
+            "left" => Some(Self::Left),
+            "right" => Some(Self::Right),
+            "center" => Some(Self::Center),
+            _ => None,
+        }
+    }
+
+    /// Convert to string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::End => "end",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Center => "center",
+        }
+    }
+}
+
+impl ImageSmoothingQuality {
+    /// Parse from string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "low" => Some(Self::Low),
+            "medium" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    /// Convert to string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+impl TextBaseline {
+    /// Parse from string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "top" => Some(Self::Top),
+            "hanging" => Some(Self::Hanging),
+            "middle" => Some(Self::Middle),
+            "alphabetic" => Some(Self::Alphabetic),
+            "ideographic" => Some(Self::Ideographic),
+            "bottom" => Some(Self::Bottom),
+            _ => None,
+        }
+    }
+
+    /// Convert to string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Top => "top",
+            Self::Hanging => "hanging",
+            Self::Middle => "middle",
+            Self::Alphabetic => "alphabetic",
+            Self::Ideographic => "ideographic",
+            Self::Bottom => "bottom",
+        }
+    }
+}
+
 impl CanvasRenderingContext2D {
     /// Create a new 2D context
     pub fn new(width: u32, height: u32) -> Self {
@@ -162,6 +258,7 @@ impl CanvasRenderingContext2D {
             data,
             states: vec![CanvasState::default()],
             current_path: Path2D::new(),
+            fonts: None,
         }
     }
     
@@ -215,38 +312,37 @@ impl CanvasRenderingContext2D {
     
     /// Fill a rectangle
     pub fn fill_rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
-        let color = match &self.state().fill_style {
-            FillStyle::Color(c) => *c,
-            _ => Color::default(),
-        };
-        
-        let alpha = self.state().global_alpha;
-        self.draw_rect(x, y, width, height, color, alpha);
+        let color = self.fill_color();
+        let state = self.state();
+        let simple = state.transform.is_identity()
+            && state.global_composite_operation == CompositeOperation::SourceOver
+            && state.clip_mask.is_none()
+            && !self.shadow_visible()
+            && [x, y, width, height].iter().all(|v| v.fract() == 0.0);
+        if simple {
+            let alpha = state.global_alpha;
+            self.draw_rect(x, y, width, height, color, alpha);
+            return;
+        }
+        let mut path = Path2D::new();
+        path.rect(x, y, width, height);
+        let polygons = self.fill_polygons(&path);
+        self.paint(&polygons, FillRule::NonZero, color);
     }
     
     /// Stroke a rectangle
     pub fn stroke_rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
-        let color = match &self.state().stroke_style {
-            StrokeStyle::Color(c) => *c,
-            _ => Color::default(),
-        };
-        
-        let line_width = self.state().line_width;
-        let alpha = self.state().global_alpha;
-        
-        // Top
-        self.draw_rect(x, y, width, line_width, color, alpha);
-        // Bottom
-        self.draw_rect(x, y + height - line_width, width, line_width, color, alpha);
-        // Left
-        self.draw_rect(x, y, line_width, height, color, alpha);
-        // Right
-        self.draw_rect(x + width - line_width, y, line_width, height, color, alpha);
+        let mut path = Path2D::new();
+        path.rect(x, y, width, height);
+        self.stroke_path(&path);
     }
     
-    /// Clear a rectangle
+    /// Clear a rectangle to transparent black (within the clip)
     pub fn clear_rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
-        self.draw_rect(x, y, width, height, Color { r: 0, g: 0, b: 0, a: 0 }, 1.0);
+        let mut path = Path2D::new();
+        path.rect(x, y, width, height);
+        let mask = Mask::fill(&self.fill_polygons(&path), self.width, self.height, FillRule::NonZero);
+        self.composite_mask(&mask, Color::rgb(0, 0, 0), 1.0, CompositeOperation::DestinationOut);
     }
     
     fn draw_rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: Color, alpha: f64) {
@@ -410,19 +506,278 @@ impl CanvasRenderingContext2D {
         self.current_path.line_to(x, y);
     }
     
+    /// Quadratic curve to point
+    pub fn quadratic_curve_to(&mut self, cpx: f64, cpy: f64, x: f64, y: f64) {
+        self.current_path.quadratic_curve_to(cpx, cpy, x, y);
+    }
+    
+    /// Bezier curve to point
+    pub fn bezier_curve_to(&mut self, cp1x: f64, cp1y: f64, cp2x: f64, cp2y: f64, x: f64, y: f64) {
+        self.current_path.bezier_curve_to(cp1x, cp1y, cp2x, cp2y, x, y);
+    }
+    
+    /// Arc
+    pub fn arc(&mut self, x: f64, y: f64, radius: f64, start_angle: f64, end_angle: f64, counterclockwise: bool) -> Result<(), CanvasError> {
+        self.current_path.arc(x, y, radius, start_angle, end_angle, counterclockwise)
+    }
+    
+    /// Arc to
+    pub fn arc_to(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, radius: f64) -> Result<(), CanvasError> {
+        self.current_path.arc_to(x1, y1, x2, y2, radius)
+    }
+    
+    /// Ellipse
+    #[allow(clippy::too_many_arguments)]
+    pub fn ellipse(&mut self, x: f64, y: f64, rx: f64, ry: f64, rotation: f64, start_angle: f64, end_angle: f64, counterclockwise: bool) -> Result<(), CanvasError> {
+        self.current_path.ellipse(x, y, rx, ry, rotation, start_angle, end_angle, counterclockwise)
+    }
+    
+    /// Rounded rectangle subpath
+    pub fn round_rect(&mut self, x: f64, y: f64, width: f64, height: f64, radii: &[f64]) -> Result<(), CanvasError> {
+        self.current_path.round_rect(x, y, width, height, radii)
+    }
+    
+    /// Rectangle subpath
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.current_path.rect(x, y, width, height);
+    }
+    
     /// Fill the current path
     pub fn fill(&mut self) {
-        // Would rasterize path and fill
+        self.fill_with_rule(FillRule::NonZero);
+    }
+    
+    /// Fill the current path with a fill rule
+    pub fn fill_with_rule(&mut self, rule: FillRule) {
+        let path = self.current_path.clone();
+        self.fill_path(&path, rule);
+    }
+    
+    /// Fill a Path2D
+    pub fn fill_path(&mut self, path: &Path2D, rule: FillRule) {
+        let polygons = self.fill_polygons(path);
+        let color = self.fill_color();
+        self.paint(&polygons, rule, color);
     }
     
     /// Stroke the current path
     pub fn stroke(&mut self) {
-        // Would rasterize path and stroke
+        let path = self.current_path.clone();
+        self.stroke_path(&path);
+    }
+    
+    /// Stroke a Path2D
+    pub fn stroke_path(&mut self, path: &Path2D) {
+        let polygons = self.stroke_polygons(path);
+        let color = self.stroke_color();
+        self.paint(&polygons, FillRule::NonZero, color);
     }
     
     /// Clip to current path
     pub fn clip(&mut self) {
-        self.state_mut().clip_path = Some(self.current_path.clone());
+        self.clip_with_rule(FillRule::NonZero);
+    }
+    
+    /// Intersect the clip region with the current path
+    pub fn clip_with_rule(&mut self, rule: FillRule) {
+        let path = self.current_path.clone();
+        self.clip_path(&path, rule);
+    }
+    
+    /// Intersect the clip region with a Path2D. The region is kept as
+    /// simplified polygons so nested clips intersect exactly and edges are
+    /// anti-aliased once
+    pub fn clip_path(&mut self, path: &Path2D, rule: FillRule) {
+        let polygons = self.fill_polygons(path);
+        let region = match &self.state().clip_region {
+            Some(clip) => boolean::op(clip, FillRule::NonZero, &polygons, rule, PathOp::Intersect),
+            None => boolean::simplify(&polygons, rule),
+        };
+        let mask = Mask::fill(&region, self.width, self.height, FillRule::NonZero);
+        let state = self.state_mut();
+        state.clip_region = Some(Arc::new(region));
+        state.clip_mask = Some(Arc::new(mask));
+    }
+    
+    /// `isPointInPath()`: hit test the current path at a canvas point
+    pub fn is_point_in_path(&self, x: f64, y: f64, rule: FillRule) -> bool {
+        self.is_point_in_path2d(&self.current_path, x, y, rule)
+    }
+    
+    /// `isPointInPath(path, ...)`: hit test a Path2D at a canvas point
+    pub fn is_point_in_path2d(&self, path: &Path2D, x: f64, y: f64, rule: FillRule) -> bool {
+        contains(&self.fill_polygons(path), x, y, rule)
+    }
+    
+    /// `isPointInStroke()`: hit test the current path's stroke, using the
+    /// line width, caps, joins and dashes
+    pub fn is_point_in_stroke(&self, x: f64, y: f64) -> bool {
+        contains(&self.stroke_polygons(&self.current_path), x, y, FillRule::NonZero)
+    }
+    
+    // Line styles
+    
+    /// `setLineDash()`; ignored if any length is negative or not finite.
+    /// Odd-length lists are repeated to make them even
+    pub fn set_line_dash(&mut self, segments: &[f64]) {
+        if segments.iter().any(|v| !v.is_finite() || *v < 0.0) {
+            return;
+        }
+        let mut pattern = segments.to_vec();
+        if pattern.len() % 2 == 1 {
+            pattern.extend_from_slice(segments);
+        }
+        self.state_mut().line_dash = pattern;
+    }
+    
+    /// `getLineDash()`
+    pub fn get_line_dash(&self) -> &[f64] {
+        &self.state().line_dash
+    }
+    
+    // Text styles
+    
+    /// Set the `font` attribute; values that don't parse as a CSS font are
+    /// ignored. Stored in serialized form
+    pub fn set_font(&mut self, font: &str) {
+        if let Some(font) = CanvasFont::parse(font) {
+            self.state_mut().font = font.to_string();
+        }
+    }
+    
+    /// Use these fonts for text instead of the system fonts
+    pub fn set_fonts(&mut self, fonts: Arc<CanvasFonts>) {
+        self.fonts = Some(fonts);
+    }
+    
+    /// Fonts used for text
+    pub fn fonts(&self) -> Arc<CanvasFonts> {
+        self.fonts.clone().unwrap_or_else(CanvasFonts::system)
+    }
+    
+    // Rasterization
+    
+    fn fill_color(&self) -> Color {
+        match &self.state().fill_style {
+            FillStyle::Color(c) => *c,
+            _ => Color::default(),
+        }
+    }
+    
+    fn stroke_color(&self) -> Color {
+        match &self.state().stroke_style {
+            StrokeStyle::Color(c) => *c,
+            _ => Color::default(),
+        }
+    }
+    
+    /// Flattening tolerance in user space for a quarter pixel on the canvas
+    fn tolerance(&self) -> f64 {
+        let m = self.state().transform;
+        0.25 / (m.a * m.d - m.b * m.c).abs().sqrt().max(1e-6)
+    }
+    
+    pub(crate) fn to_canvas(&self, polygons: impl IntoIterator<Item = Vec<(f64, f64)>>) -> Vec<Vec<(f64, f64)>> {
+        let m = self.state().transform;
+        polygons
+            .into_iter()
+            .map(|p| p.into_iter().map(|(x, y)| m.transform_point(x, y)).collect())
+            .collect()
+    }
+    
+    /// Subpaths of a path as canvas-space polygons
+    fn fill_polygons(&self, path: &Path2D) -> Vec<Vec<(f64, f64)>> {
+        self.to_canvas(path.flatten(self.tolerance()).into_iter().map(|l| l.points))
+    }
+    
+    /// Stroke outline of a path as canvas-space polygons; the stroke is built
+    /// in user space so transforms scale and skew the pen
+    fn stroke_polygons(&self, path: &Path2D) -> Vec<Vec<(f64, f64)>> {
+        let state = self.state();
+        let mut lines = path.flatten(self.tolerance());
+        if !state.line_dash.is_empty() {
+            lines = dash(&lines, &state.line_dash, state.line_dash_offset);
+        }
+        let params = StrokeParams {
+            width: state.line_width,
+            cap: state.line_cap,
+            join: state.line_join,
+            miter_limit: state.miter_limit,
+        };
+        self.to_canvas(outline(&lines, &params))
+    }
+    
+    pub(crate) fn shadow_visible(&self) -> bool {
+        let state = self.state();
+        state.shadow_color.a != 0
+            && (state.shadow_blur > 0.0 || state.shadow_offset_x != 0.0 || state.shadow_offset_y != 0.0)
+    }
+    
+    /// Draw canvas-space polygons: the shadow first, then the shape
+    fn paint(&mut self, polygons: &[Vec<(f64, f64)>], rule: FillRule, color: Color) {
+        let state = self.state();
+        let (alpha, op) = (state.global_alpha, state.global_composite_operation);
+        if self.shadow_visible() {
+            // Shadow offsets are in canvas pixels, unaffected by the transform
+            let (dx, dy) = (state.shadow_offset_x, state.shadow_offset_y);
+            let (blur, shadow_color) = (state.shadow_blur, state.shadow_color);
+            let shifted: Vec<Vec<(f64, f64)>> = polygons
+                .iter()
+                .map(|p| p.iter().map(|&(x, y)| (x + dx, y + dy)).collect())
+                .collect();
+            let mut shadow = Mask::fill(&shifted, self.width, self.height, rule);
+            shadow.blur(blur / 2.0);
+            self.composite_mask(&shadow, shadow_color, alpha * color.a as f64 / 255.0, op);
+        }
+        let mask = Mask::fill(polygons, self.width, self.height, rule);
+        self.composite_mask(&mask, color, alpha, op);
+    }
+    
+    /// Composite a solid color through a coverage mask and the clip
+    fn composite_mask(&mut self, mask: &Mask, color: Color, alpha: f64, op: CompositeOperation) {
+        let rgba = [color.r, color.g, color.b, color.a].map(|c| c as f64 / 255.0);
+        self.composite_source(mask, alpha, op, |_, _| rgba);
+    }
+    
+    /// Composite a per-pixel source (non-premultiplied RGBA in 0..=1)
+    /// through a coverage mask and the clip
+    pub(crate) fn composite_source(
+        &mut self,
+        mask: &Mask,
+        alpha: f64,
+        op: CompositeOperation,
+        source: impl Fn(u32, u32) -> [f64; 4],
+    ) {
+        let clip = self.state().clip_mask.clone();
+        let unbounded = op.is_unbounded();
+        let (x0, y0, x1, y1) = if unbounded { (0, 0, self.width, self.height) } else { mask.bounds() };
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let coverage = mask.get(x, y) as f64;
+                if coverage == 0.0 && !unbounded {
+                    continue;
+                }
+                let clip_coverage = clip.as_ref().map_or(1.0, |c| c.get(x, y) as f64);
+                if clip_coverage == 0.0 {
+                    continue;
+                }
+                let src = if coverage > 0.0 { source(x, y) } else { [0.0; 4] };
+                let idx = ((y * self.width + x) * 4) as usize;
+                let dst = [0, 1, 2, 3].map(|i| self.data[idx + i] as f64 / 255.0);
+                let out = composite([src[0], src[1], src[2], src[3] * alpha * coverage], dst, op);
+                // Partially clipped pixels mix the result with the destination
+                let a = dst[3] + (out[3] - dst[3]) * clip_coverage;
+                for i in 0..3 {
+                    let c = if a > 0.0 {
+                        (dst[i] * dst[3] + (out[i] * out[3] - dst[i] * dst[3]) * clip_coverage) / a
+                    } else {
+                        0.0
+                    };
+                    self.data[idx + i] = (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+                }
+                self.data[idx + 3] = (a.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
     }
 }
 
@@ -447,7 +802,10 @@ impl Default for CanvasState {
             shadow_offset_y: 0.0,
             shadow_blur: 0.0,
             shadow_color: Color::default(),
-            clip_path: None,
+            image_smoothing_enabled: true,
+            image_smoothing_quality: ImageSmoothingQuality::default(),
+            clip_mask: None,
+            clip_region: None,
         }
     }
 }
@@ -494,4 +852,81 @@ mod tests {
         ctx.restore();
         assert_eq!(ctx.state().global_alpha, 0.5);
     }
+    
+    fn pixel(ctx: &CanvasRenderingContext2D, x: u32, y: u32) -> [u8; 4] {
+        let idx = ((y * ctx.width() + x) * 4) as usize;
+        ctx.data()[idx..idx + 4].try_into().unwrap()
+    }
+    
+    #[test]
+    fn test_clip_intersection() {
+        let mut ctx = CanvasRenderingContext2D::new(40, 40);
+        ctx.save();
+        ctx.rect(0.0, 0.0, 20.0, 40.0);
+        ctx.clip();
+        ctx.begin_path();
+        ctx.rect(0.0, 10.0, 40.0, 10.0);
+        ctx.clip();
+        ctx.fill_rect(0.0, 0.0, 40.0, 40.0);
+        
+        assert_eq!(pixel(&ctx, 5, 15)[3], 255);
+        assert_eq!(pixel(&ctx, 5, 5)[3], 0);
+        assert_eq!(pixel(&ctx, 30, 15)[3], 0);
+        
+        // Restore drops the clip
+        ctx.restore();
+        assert!(ctx.state().clip_mask.is_none());
+        ctx.clear_rect(0.0, 0.0, 40.0, 40.0);
+        assert_eq!(pixel(&ctx, 5, 15), [0, 0, 0, 0]);
+    }
+    
+    #[test]
+    fn test_stroke_with_dash() {
+        let mut ctx = CanvasRenderingContext2D::new(40, 10);
+        ctx.state_mut().line_width = 4.0;
+        ctx.set_line_dash(&[5.0]);
+        assert_eq!(ctx.get_line_dash(), &[5.0, 5.0]);
+        ctx.move_to(0.0, 5.0);
+        ctx.line_to(40.0, 5.0);
+        ctx.stroke();
+        
+        assert_eq!(pixel(&ctx, 2, 5)[3], 255);
+        assert_eq!(pixel(&ctx, 7, 5)[3], 0);
+        assert_eq!(pixel(&ctx, 12, 5)[3], 255);
+        assert_eq!(pixel(&ctx, 2, 1)[3], 0);
+        assert!(ctx.is_point_in_stroke(2.0, 4.0));
+        assert!(!ctx.is_point_in_stroke(7.0, 5.0));
+    }
+    
+    #[test]
+    fn test_shadow_and_composite() {
+        let mut ctx = CanvasRenderingContext2D::new(40, 40);
+        ctx.state_mut().shadow_color = Color::rgba(0, 0, 255, 255);
+        ctx.state_mut().shadow_offset_x = 20.0;
+        ctx.state_mut().fill_style = FillStyle::Color(Color::rgb(255, 0, 0));
+        ctx.fill_rect(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(pixel(&ctx, 5, 5), [255, 0, 0, 255]);
+        assert_eq!(pixel(&ctx, 25, 5), [0, 0, 255, 255]);
+        
+        // destination-in keeps the destination only where the source is
+        let mut ctx = CanvasRenderingContext2D::new(40, 40);
+        ctx.fill_rect(0.0, 0.0, 40.0, 40.0);
+        ctx.state_mut().global_composite_operation = CompositeOperation::DestinationIn;
+        ctx.fill_rect(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(pixel(&ctx, 5, 5)[3], 255);
+        assert_eq!(pixel(&ctx, 20, 20)[3], 0);
+    }
+    
+    #[test]
+    fn test_point_in_path() {
+        let mut ctx = CanvasRenderingContext2D::new(100, 100);
+        ctx.state_mut().transform = TransformMatrix::translate(50.0, 0.0);
+        ctx.rect(0.0, 0.0, 40.0, 40.0);
+        ctx.rect(10.0, 10.0, 20.0, 20.0);
+        
+        assert!(ctx.is_point_in_path(55.0, 5.0, FillRule::NonZero));
+        assert!(ctx.is_point_in_path(70.0, 20.0, FillRule::NonZero));
+        assert!(!ctx.is_point_in_path(70.0, 20.0, FillRule::EvenOdd));
+        assert!(!ctx.is_point_in_path(5.0, 5.0, FillRule::NonZero));
+    }
 }
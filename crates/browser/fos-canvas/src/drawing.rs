@@ -1,56 +1,89 @@
 //! Image Drawing
 //!
-//! Canvas 2D image methods.
+//! Canvas 2D `drawImage()`. The destination rectangle is transformed and
+//! rasterized like any shape; each covered pixel maps back through the
+//! inverse transform to the source rectangle and samples it with nearest or
+//! bilinear filtering. Filtering happens on premultiplied colors so
+//! transparent texels don't darken edges.
 
+use fos_render::image::{ImageCache, ImageKey};
+
+use crate::context2d::{CanvasRenderingContext2D, ImageSmoothingQuality};
 use crate::image_data::ImageData;
+use crate::offscreen::ImageBitmap;
+use crate::raster::{FillRule, Mask};
+use crate::transforms::TransformMatrix;
 
 /// Image source for drawImage
 #[derive(Debug, Clone)]
 pub enum CanvasImageSource {
     ImageData(ImageData),
-    ImageBitmap { data: Vec<u8>, width: u32, height: u32 },
+    /// Decoded `<img>` pixels (RGBA)
+    Image { pixels: Vec<u8>, width: u32, height: u32 },
+    ImageBitmap(ImageBitmap),
     Canvas { data: Vec<u8>, width: u32, height: u32 },
+    /// Current video frame (RGBA)
     Video { frame: Vec<u8>, width: u32, height: u32 },
 }
 
 impl CanvasImageSource {
+    /// Source for a decoded image in the cache
+    pub fn from_image_cache(cache: &mut ImageCache, key: &ImageKey) -> Option<Self> {
+        let image = cache.get(key)?;
+        Some(Self::Image { pixels: image.pixels.clone(), width: image.width, height: image.height })
+    }
+
+    /// Snapshot of another canvas (or the same one)
+    pub fn from_canvas(canvas: &CanvasRenderingContext2D) -> Self {
+        Self::Canvas { data: canvas.data().to_vec(), width: canvas.width(), height: canvas.height() }
+    }
+
     pub fn width(&self) -> u32 {
         match self {
             Self::ImageData(img) => img.width(),
-            Self::ImageBitmap { width, .. } => *width,
+            Self::Image { width, .. } => *width,
+            Self::ImageBitmap(bitmap) => bitmap.width(),
             Self::Canvas { width, .. } => *width,
             Self::Video { width, .. } => *width,
         }
     }
-    
+
     pub fn height(&self) -> u32 {
         match self {
             Self::ImageData(img) => img.height(),
-            Self::ImageBitmap { height, .. } => *height,
+            Self::Image { height, .. } => *height,
+            Self::ImageBitmap(bitmap) => bitmap.height(),
             Self::Canvas { height, .. } => *height,
             Self::Video { height, .. } => *height,
         }
     }
-    
+
     pub fn data(&self) -> &[u8] {
         match self {
             Self::ImageData(img) => img.data(),
-            Self::ImageBitmap { data, .. } => data,
+            Self::Image { pixels, .. } => pixels,
+            Self::ImageBitmap(bitmap) => bitmap.data(),
             Self::Canvas { data, .. } => data,
             Self::Video { frame, .. } => frame,
         }
     }
+
+    /// Whether the color channels are premultiplied by alpha
+    pub fn premultiplied_alpha(&self) -> bool {
+        matches!(self, Self::ImageBitmap(bitmap) if bitmap.premultiplied_alpha())
+    }
 }
 
 /// Image drawing trait for CanvasRenderingContext2D
 pub trait ImageDrawing {
     /// Draw image at position
     fn draw_image(&mut self, image: &CanvasImageSource, dx: f64, dy: f64);
-    
+
     /// Draw image with size
     fn draw_image_scaled(&mut self, image: &CanvasImageSource, dx: f64, dy: f64, dwidth: f64, dheight: f64);
-    
+
     /// Draw image with source and destination rectangles
+    #[allow(clippy::too_many_arguments)]
     fn draw_image_full(
         &mut self,
         image: &CanvasImageSource,
@@ -59,11 +92,11 @@ pub trait ImageDrawing {
     );
 }
 
-impl ImageDrawing for super::context2d::CanvasRenderingContext2D {
+impl ImageDrawing for CanvasRenderingContext2D {
     fn draw_image(&mut self, image: &CanvasImageSource, dx: f64, dy: f64) {
         self.draw_image_scaled(image, dx, dy, image.width() as f64, image.height() as f64);
     }
-    
+
     fn draw_image_scaled(&mut self, image: &CanvasImageSource, dx: f64, dy: f64, dwidth: f64, dheight: f64) {
         self.draw_image_full(
             image,
@@ -71,63 +104,216 @@ impl ImageDrawing for super::context2d::CanvasRenderingContext2D {
             dx, dy, dwidth, dheight,
         );
     }
-    
+
     fn draw_image_full(
         &mut self,
         image: &CanvasImageSource,
         sx: f64, sy: f64, swidth: f64, sheight: f64,
         dx: f64, dy: f64, dwidth: f64, dheight: f64,
     ) {
-        let src_data = image.data();
-        let src_width = image.width() as usize;
-        
-        let scale_x = swidth / dwidth;
-        let scale_y = sheight / dheight;
-        
-        // Get dimensions before mutable borrow
-        let dest_width = self.width() as usize;
-        let dest_height = self.height() as usize;
-        let dest_data = self.data_mut();
-        
-        for py in 0..(dheight as usize) {
-            for px in 0..(dwidth as usize) {
-                let dest_x = (dx as usize) + px;
-                let dest_y = (dy as usize) + py;
-                
-                if dest_x >= dest_width || dest_y >= dest_height {
-                    continue;
-                }
-                
-                let src_x = (sx + (px as f64 * scale_x)) as usize;
-                let src_y = (sy + (py as f64 * scale_y)) as usize;
-                
-                if src_x < src_width && src_y < (sheight as usize) {
-                    let src_idx = (src_y * src_width + src_x) * 4;
-                    let dst_idx = (dest_y * dest_width + dest_x) * 4;
-                    
-                    if src_idx + 3 < src_data.len() && dst_idx + 3 < dest_data.len() {
-                        dest_data[dst_idx] = src_data[src_idx];
-                        dest_data[dst_idx + 1] = src_data[src_idx + 1];
-                        dest_data[dst_idx + 2] = src_data[src_idx + 2];
-                        dest_data[dst_idx + 3] = src_data[src_idx + 3];
-                    }
+        let (width, height) = (image.width(), image.height());
+        if width == 0 || height == 0 || image.data().len() < (width * height * 4) as usize {
+            return;
+        }
+        let src = normalize(sx, sy, swidth, sheight);
+        let dst = normalize(dx, dy, dwidth, dheight);
+        if src.2 == 0.0 || src.3 == 0.0 || dst.2 == 0.0 || dst.3 == 0.0 {
+            return;
+        }
+        // Clip the source rectangle to the image, shrinking the destination to match
+        let (scale_x, scale_y) = (dst.2 / src.2, dst.3 / src.3);
+        let (x0, y0) = (src.0.max(0.0), src.1.max(0.0));
+        let (x1, y1) = ((src.0 + src.2).min(width as f64), (src.1 + src.3).min(height as f64));
+        if x1 <= x0 || y1 <= y0 {
+            return;
+        }
+        let dst = (dst.0 + (x0 - src.0) * scale_x, dst.1 + (y0 - src.1) * scale_y, (x1 - x0) * scale_x, (y1 - y0) * scale_y);
+        let src = (x0, y0, x1 - x0, y1 - y0);
+
+        let state = self.state();
+        let Some(inverse) = state.transform.invert() else { return };
+        let sampler = Sampler {
+            image,
+            src,
+            dst,
+            inverse,
+            smoothing: state.image_smoothing_enabled,
+            taps: supersampling(state.image_smoothing_quality, &inverse, src.2 / dst.2, src.3 / dst.3),
+        };
+        let (alpha, op) = (state.global_alpha, state.global_composite_operation);
+        let (dx, dy, dw, dh) = dst;
+        let quad = self.to_canvas([vec![(dx, dy), (dx + dw, dy), (dx + dw, dy + dh), (dx, dy + dh)]]);
+
+        if self.shadow_visible() {
+            // The shadow takes the image's alpha, offset in canvas pixels
+            let state = self.state();
+            let (ox, oy) = (state.shadow_offset_x, state.shadow_offset_y);
+            let (blur, color) = (state.shadow_blur, state.shadow_color);
+            let shifted: Vec<Vec<(f64, f64)>> = quad
+                .iter()
+                .map(|p| p.iter().map(|&(x, y)| (x + ox, y + oy)).collect())
+                .collect();
+            let mut shadow = Mask::fill(&shifted, self.width(), self.height(), FillRule::NonZero);
+            shadow.modulate(|x, y| sampler.sample(x as f64 + 0.5 - ox, y as f64 + 0.5 - oy)[3] as f32);
+            shadow.blur(blur / 2.0);
+            let rgba = [color.r, color.g, color.b, color.a].map(|c| c as f64 / 255.0);
+            self.composite_source(&shadow, alpha, op, |_, _| rgba);
+        }
+        let mask = Mask::fill(&quad, self.width(), self.height(), FillRule::NonZero);
+        self.composite_source(&mask, alpha, op, |x, y| sampler.sample(x as f64 + 0.5, y as f64 + 0.5));
+    }
+}
+
+/// Rectangle with negative sizes flipped to positive
+fn normalize(x: f64, y: f64, w: f64, h: f64) -> (f64, f64, f64, f64) {
+    (x.min(x + w), y.min(y + h), w.abs(), h.abs())
+}
+
+/// Samples per axis per canvas pixel: more when medium/high quality
+/// smoothing minifies
+fn supersampling(quality: ImageSmoothingQuality, inverse: &TransformMatrix, sx: f64, sy: f64) -> u32 {
+    let max = match quality {
+        ImageSmoothingQuality::Low => return 1,
+        ImageSmoothingQuality::Medium => 2,
+        ImageSmoothingQuality::High => 4,
+    };
+    // Source texels covered by one canvas pixel along each axis
+    let fx = inverse.a.hypot(inverse.b) * sx;
+    let fy = inverse.c.hypot(inverse.d) * sy;
+    (fx.max(fy).ceil() as u32).clamp(1, max)
+}
+
+/// Maps canvas points to filtered source colors
+struct Sampler<'a> {
+    image: &'a CanvasImageSource,
+    /// Source rectangle in image pixels
+    src: (f64, f64, f64, f64),
+    /// Destination rectangle in user space
+    dst: (f64, f64, f64, f64),
+    /// Canvas to user space
+    inverse: TransformMatrix,
+    smoothing: bool,
+    taps: u32,
+}
+
+impl Sampler<'_> {
+    /// Non-premultiplied color at a canvas point
+    fn sample(&self, x: f64, y: f64) -> [f64; 4] {
+        let n = self.taps;
+        let mut sum = [0.0; 4];
+        for j in 0..n {
+            for i in 0..n {
+                let (px, py) = if n == 1 {
+                    (x, y)
+                } else {
+                    (x - 0.5 + (i as f64 + 0.5) / n as f64, y - 0.5 + (j as f64 + 0.5) / n as f64)
+                };
+                let texel = self.sample_point(px, py);
+                for k in 0..4 {
+                    sum[k] += texel[k];
                 }
             }
         }
+        let count = (n * n) as f64;
+        let a = sum[3] / count;
+        if a <= 0.0 {
+            return [0.0; 4];
+        }
+        [sum[0] / count / a, sum[1] / count / a, sum[2] / count / a, a]
+    }
+
+    /// Premultiplied color at a canvas point
+    fn sample_point(&self, x: f64, y: f64) -> [f64; 4] {
+        let (ux, uy) = self.inverse.transform_point(x, y);
+        let (sx, sy, sw, sh) = self.src;
+        let (dx, dy, dw, dh) = self.dst;
+        let u = sx + (ux - dx) * sw / dw;
+        let v = sy + (uy - dy) * sh / dh;
+        if !self.smoothing {
+            return self.texel(u.floor(), v.floor());
+        }
+        // Bilinear between the four nearest texel centers
+        let (u, v) = (u - 0.5, v - 0.5);
+        let (u0, v0) = (u.floor(), v.floor());
+        let (fu, fv) = (u - u0, v - v0);
+        let t00 = self.texel(u0, v0);
+        let t10 = self.texel(u0 + 1.0, v0);
+        let t01 = self.texel(u0, v0 + 1.0);
+        let t11 = self.texel(u0 + 1.0, v0 + 1.0);
+        let mut out = [0.0; 4];
+        for k in 0..4 {
+            let top = t00[k] + (t10[k] - t00[k]) * fu;
+            let bottom = t01[k] + (t11[k] - t01[k]) * fu;
+            out[k] = top + (bottom - top) * fv;
+        }
+        out
+    }
+
+    /// Premultiplied texel, clamped to the source rectangle
+    fn texel(&self, u: f64, v: f64) -> [f64; 4] {
+        let (sx, sy, sw, sh) = self.src;
+        let x = u.clamp(sx.floor(), (sx + sw).ceil() - 1.0) as usize;
+        let y = v.clamp(sy.floor(), (sy + sh).ceil() - 1.0) as usize;
+        let idx = (y * self.image.width() as usize + x) * 4;
+        let px = &self.image.data()[idx..idx + 4];
+        let a = px[3] as f64 / 255.0;
+        let scale = if self.image.premultiplied_alpha() { 1.0 / 255.0 } else { a / 255.0 };
+        [px[0] as f64 * scale, px[1] as f64 * scale, px[2] as f64 * scale, a]
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::CanvasRenderingContext2D;
-    
+
+    fn pixel(ctx: &CanvasRenderingContext2D, x: u32, y: u32) -> [u8; 4] {
+        let idx = ((y * ctx.width() + x) * 4) as usize;
+        ctx.data()[idx..idx + 4].try_into().unwrap()
+    }
+
+    /// 2x1 image: opaque red, transparent black
+    fn two_pixels() -> CanvasImageSource {
+        CanvasImageSource::Image { pixels: vec![255, 0, 0, 255, 0, 0, 0, 0], width: 2, height: 1 }
+    }
+
     #[test]
     fn test_draw_image() {
         let mut ctx = CanvasRenderingContext2D::new(100, 100);
         let img = ImageData::new(10, 10);
         let src = CanvasImageSource::ImageData(img);
-        
+
         ctx.draw_image(&src, 0.0, 0.0);
+
+        // Source cropping: only the red texel, scaled up
+        ctx.draw_image_full(&two_pixels(), 0.0, 0.0, 1.0, 1.0, 10.0, 10.0, 4.0, 4.0);
+        assert_eq!(pixel(&ctx, 13, 13), [255, 0, 0, 255]);
+        assert_eq!(pixel(&ctx, 14, 13), [0, 0, 0, 0]);
+
+        // Canvas sources snapshot the pixels, so drawing onto itself works
+        let copy = CanvasImageSource::from_canvas(&ctx);
+        ctx.draw_image_full(&copy, 10.0, 10.0, 4.0, 4.0, 50.0, 50.0, -4.0, 4.0);
+        assert_eq!(pixel(&ctx, 47, 51), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_smoothing_and_premultiplied_alpha() {
+        let mut ctx = CanvasRenderingContext2D::new(8, 1);
+        ctx.state_mut().image_smoothing_enabled = false;
+        ctx.draw_image_scaled(&two_pixels(), 0.0, 0.0, 8.0, 1.0);
+        assert_eq!(pixel(&ctx, 3, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&ctx, 4, 0), [0, 0, 0, 0]);
+
+        // Bilinear blends alpha but keeps the color: no dark fringe
+        let mut ctx = CanvasRenderingContext2D::new(8, 1);
+        ctx.draw_image_scaled(&two_pixels(), 0.0, 0.0, 8.0, 1.0);
+        let mid = pixel(&ctx, 4, 0);
+        assert_eq!(&mid[..3], &[255, 0, 0]);
+        assert!(mid[3] > 0 && mid[3] < 255);
+
+        // Premultiplied bitmaps are unpremultiplied when drawn
+        let bitmap = ImageBitmap::from_data(vec![40, 0, 0, 100], 1, 1, true);
+        let mut ctx = CanvasRenderingContext2D::new(1, 1);
+        ctx.draw_image(&CanvasImageSource::ImageBitmap(bitmap), 0.0, 0.0);
+        assert_eq!(pixel(&ctx, 0, 0), [102, 0, 0, 100]);
     }
 }
@@ -0,0 +1,340 @@
+//! createImageBitmap
+//!
+//! Asynchronous ImageBitmap creation from encoded blobs and URLs. Requests go
+//! through the render decode queue; each dequeued request is fetched, decoded,
+//! resized and premultiplied on its own thread, and finished bitmaps are
+//! collected by `poll`.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use fos_render::image::{DecodePriority, DecodeQueue, DecodeRequest, ImageDecoder};
+
+use crate::context2d::{CanvasRenderingContext2D, ImageSmoothingQuality};
+use crate::drawing::{CanvasImageSource, ImageDrawing};
+use crate::offscreen::{ImageBitmap, ResizeQuality};
+use crate::CanvasError;
+
+/// Source of an asynchronously decoded bitmap
+#[derive(Debug, Clone)]
+pub enum ImageBitmapSource {
+    /// Encoded image bytes
+    Blob(Vec<u8>),
+    /// Image URL, loaded through the decoder's fetcher
+    Url(String),
+}
+
+/// `premultiplyAlpha` option
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PremultiplyAlpha {
+    #[default]
+    Default,
+    Premultiply,
+    None,
+}
+
+impl PremultiplyAlpha {
+    /// Parse from string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "default" => Some(Self::Default),
+            "premultiply" => Some(Self::Premultiply),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    /// Convert to string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Premultiply => "premultiply",
+            Self::None => "none",
+        }
+    }
+}
+
+/// `imageOrientation` option
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageOrientation {
+    #[default]
+    FromImage,
+    FlipY,
+}
+
+impl ImageOrientation {
+    /// Parse from string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "from-image" | "none" => Some(Self::FromImage),
+            "flipY" => Some(Self::FlipY),
+            _ => None,
+        }
+    }
+
+    /// Convert to string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FromImage => "from-image",
+            Self::FlipY => "flipY",
+        }
+    }
+}
+
+/// ImageBitmapOptions
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImageBitmapOptions {
+    pub resize_width: Option<u32>,
+    pub resize_height: Option<u32>,
+    pub resize_quality: ResizeQuality,
+    pub premultiply_alpha: PremultiplyAlpha,
+    pub image_orientation: ImageOrientation,
+}
+
+/// Loads the bytes behind an image URL
+pub trait ImageFetcher: Send + Sync + Debug {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, String>;
+}
+
+/// Result of one createImageBitmap request
+pub type ImageBitmapResult = Result<ImageBitmap, CanvasError>;
+
+/// Runs createImageBitmap requests on the decode queue
+pub struct ImageBitmapDecoder {
+    queue: DecodeQueue,
+    pending: HashMap<u64, (ImageBitmapSource, ImageBitmapOptions)>,
+    fetcher: Option<Arc<dyn ImageFetcher>>,
+    sender: Sender<(u64, ImageBitmapResult)>,
+    receiver: Receiver<(u64, ImageBitmapResult)>,
+    next_id: u64,
+}
+
+impl Default for ImageBitmapDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImageBitmapDecoder {
+    /// Create a decoder without URL support
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            queue: DecodeQueue::default(),
+            pending: HashMap::new(),
+            fetcher: None,
+            sender,
+            receiver,
+            next_id: 1,
+        }
+    }
+
+    /// Create a decoder that loads URLs through `fetcher`
+    pub fn with_fetcher(fetcher: Arc<dyn ImageFetcher>) -> Self {
+        Self { fetcher: Some(fetcher), ..Self::new() }
+    }
+
+    /// `createImageBitmap(source, options)`: queue a decode and return its id
+    pub fn create_image_bitmap(&mut self, source: ImageBitmapSource, options: ImageBitmapOptions) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let url = match &source {
+            ImageBitmapSource::Blob(_) => format!("blob:{}", id),
+            ImageBitmapSource::Url(url) => url.clone(),
+        };
+        self.queue.enqueue(DecodeRequest {
+            id,
+            url,
+            width: options.resize_width.unwrap_or(0),
+            height: options.resize_height.unwrap_or(0),
+            priority: DecodePriority::Normal,
+            viewport_distance: 0,
+        });
+        self.pending.insert(id, (source, options));
+        id
+    }
+
+    /// Start queued decodes and return the requests that have settled
+    pub fn poll(&mut self) -> Vec<(u64, ImageBitmapResult)> {
+        while let Some(request) = self.queue.dequeue() {
+            let Some((source, options)) = self.pending.remove(&request.id) else {
+                self.queue.complete(request.id);
+                continue;
+            };
+            let fetcher = self.fetcher.clone();
+            let sender = self.sender.clone();
+            thread::spawn(move || {
+                let result = load(source, fetcher.as_deref()).and_then(|bytes| decode(&bytes, &options));
+                let _ = sender.send((request.id, result));
+            });
+        }
+
+        let mut settled = Vec::new();
+        while let Ok((id, result)) = self.receiver.try_recv() {
+            self.queue.complete(id);
+            settled.push((id, result));
+        }
+        settled
+    }
+
+    /// Whether any request is queued or decoding
+    pub fn is_idle(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl Debug for ImageBitmapDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImageBitmapDecoder")
+            .field("pending", &self.pending.len())
+            .field("fetcher", &self.fetcher)
+            .finish()
+    }
+}
+
+fn load(source: ImageBitmapSource, fetcher: Option<&dyn ImageFetcher>) -> Result<Vec<u8>, CanvasError> {
+    match source {
+        ImageBitmapSource::Blob(bytes) => Ok(bytes),
+        ImageBitmapSource::Url(url) => fetcher
+            .ok_or_else(|| CanvasError::NotSupported(format!("no fetcher for {}", url)))?
+            .fetch(&url)
+            .map_err(CanvasError::InvalidState),
+    }
+}
+
+/// Decode and apply resize, orientation and premultiply options
+pub fn decode(bytes: &[u8], options: &ImageBitmapOptions) -> Result<ImageBitmap, CanvasError> {
+    let image = ImageDecoder::decode(bytes).map_err(|e| CanvasError::InvalidState(e.to_string()))?;
+    if image.width == 0 || image.height == 0 {
+        return Err(CanvasError::InvalidState("image has no pixels".into()));
+    }
+
+    // A single resize dimension keeps the aspect ratio
+    let (width, height) = match (options.resize_width, options.resize_height) {
+        (Some(w), Some(h)) => (w, h),
+        (Some(w), None) => (w, (w as u64 * image.height as u64 / image.width as u64).max(1) as u32),
+        (None, Some(h)) => ((h as u64 * image.width as u64 / image.height as u64).max(1) as u32, h),
+        (None, None) => (image.width, image.height),
+    };
+    if width == 0 || height == 0 {
+        return Err(CanvasError::InvalidState("resize dimensions must be positive".into()));
+    }
+
+    let mut pixels = if (width, height) == (image.width, image.height) {
+        image.pixels
+    } else {
+        let mut ctx = CanvasRenderingContext2D::new(width, height);
+        let state = ctx.state_mut();
+        match options.resize_quality {
+            ResizeQuality::Pixelated => state.image_smoothing_enabled = false,
+            ResizeQuality::Low => state.image_smoothing_quality = ImageSmoothingQuality::Low,
+            ResizeQuality::Medium => state.image_smoothing_quality = ImageSmoothingQuality::Medium,
+            ResizeQuality::High => state.image_smoothing_quality = ImageSmoothingQuality::High,
+        }
+        let source = CanvasImageSource::Image { pixels: image.pixels, width: image.width, height: image.height };
+        ctx.draw_image_scaled(&source, 0.0, 0.0, width as f64, height as f64);
+        ctx.data().to_vec()
+    };
+
+    if options.image_orientation == ImageOrientation::FlipY {
+        let row = width as usize * 4;
+        let rows = height as usize;
+        for y in 0..rows / 2 {
+            let (top, bottom) = pixels.split_at_mut((rows - 1 - y) * row);
+            top[y * row..(y + 1) * row].swap_with_slice(&mut bottom[..row]);
+        }
+    }
+
+    let premultiplied = options.premultiply_alpha == PremultiplyAlpha::Premultiply;
+    if premultiplied {
+        for px in pixels.chunks_exact_mut(4) {
+            let a = px[3] as u32;
+            for c in &mut px[..3] {
+                *c = ((*c as u32 * a + 127) / 255) as u8;
+            }
+        }
+    }
+
+    Ok(ImageBitmap::from_data(pixels, width, height, premultiplied).with_resize_quality(options.resize_quality))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 2x1 RGBA PNG, stored uncompressed: opaque red, then blue at half alpha
+    const PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+        0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0xf4, 0x22, 0x7f,
+        0x8a, 0x00, 0x00, 0x00, 0x14, 0x49, 0x44, 0x41, 0x54, 0x78, 0x01, 0x01, 0x09, 0x00, 0xf6, 0xff,
+        0x00, 0xff, 0x00, 0x00, 0xff, 0x00, 0x00, 0xff, 0x80, 0x0f, 0x7a, 0x03, 0x7e, 0x4a, 0x6e, 0x70,
+        0x0c, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn test_decode_options() {
+        let bitmap = decode(PNG, &ImageBitmapOptions::default()).unwrap();
+        assert_eq!((bitmap.width(), bitmap.height()), (2, 1));
+        assert_eq!(bitmap.data(), &[255, 0, 0, 255, 0, 0, 255, 128]);
+        assert!(!bitmap.premultiplied_alpha());
+
+        let options = ImageBitmapOptions { premultiply_alpha: PremultiplyAlpha::Premultiply, ..Default::default() };
+        let bitmap = decode(PNG, &options).unwrap();
+        assert_eq!(&bitmap.data()[4..], &[0, 0, 128, 128]);
+
+        // Pixelated upscale repeats source pixels; height follows the aspect ratio
+        let options = ImageBitmapOptions {
+            resize_width: Some(4),
+            resize_quality: ResizeQuality::Pixelated,
+            ..Default::default()
+        };
+        let bitmap = decode(PNG, &options).unwrap();
+        assert_eq!((bitmap.width(), bitmap.height()), (4, 2));
+        assert_eq!(&bitmap.data()[4..8], &[255, 0, 0, 255]);
+        assert_eq!(&bitmap.data()[8..12], &[0, 0, 255, 128]);
+        assert_eq!(bitmap.resize_quality(), ResizeQuality::Pixelated);
+    }
+
+    #[derive(Debug)]
+    struct StaticFetcher;
+
+    impl ImageFetcher for StaticFetcher {
+        fn fetch(&self, url: &str) -> Result<Vec<u8>, String> {
+            match url {
+                "https://example.com/a.png" => Ok(PNG.to_vec()),
+                _ => Err(format!("404 {}", url)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_async_decode() {
+        let mut decoder = ImageBitmapDecoder::with_fetcher(Arc::new(StaticFetcher));
+        let blob = decoder.create_image_bitmap(ImageBitmapSource::Blob(PNG.to_vec()), ImageBitmapOptions::default());
+        let url = decoder.create_image_bitmap(
+            ImageBitmapSource::Url("https://example.com/a.png".into()),
+            ImageBitmapOptions::default(),
+        );
+        let missing = decoder.create_image_bitmap(
+            ImageBitmapSource::Url("https://example.com/missing.png".into()),
+            ImageBitmapOptions::default(),
+        );
+
+        let mut settled = HashMap::new();
+        for _ in 0..500 {
+            settled.extend(decoder.poll());
+            if settled.len() == 3 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(decoder.is_idle());
+        assert_eq!(settled[&blob].as_ref().unwrap().width(), 2);
+        assert_eq!(settled[&url].as_ref().unwrap().data()[0], 255);
+        assert!(settled[&missing].is_err());
+    }
+}
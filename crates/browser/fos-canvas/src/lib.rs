@@ -4,20 +4,28 @@
 //!
 //! Features:
 //! - CanvasRenderingContext2D with SIMD acceleration
-//! - Path2D
+//! - Path2D with anti-aliased fill, stroke, dashes and clipping
+//! - Path boolean operations and transformed addPath
 //! - ImageData
-//! - Transforms and compositing
-//! - OffscreenCanvas
-//! - WebGL 1.0 with resource pooling
+//! - drawImage from images, canvases, bitmaps and video frames
+//! - Transforms, shadows and all composite operations
+//! - Text shaped and outlined with fos-text
+//! - OffscreenCanvas, transferable to worker threads
+//! - createImageBitmap with async decode, resize and premultiply
+//! - WebGL 1.0 and 2.0 executing draw calls with GLSL ES translation
 
 pub mod simd;
 pub mod pool;
 pub mod context2d;
 pub mod path;
+pub mod raster;
+pub mod boolean;
+pub mod stroke;
 pub mod transforms;
 pub mod compositing;
 pub mod image_data;
 pub mod offscreen;
+pub mod image_bitmap;
 pub mod text;
 pub mod drawing;
 pub mod webgl;
@@ -25,14 +33,20 @@ pub mod webgl;
 pub use context2d::{
     CanvasRenderingContext2D, CanvasState, Color,
     FillStyle, StrokeStyle, Gradient, Pattern,
-    LineCap, LineJoin, TextAlign, TextBaseline,
+    LineCap, LineJoin, TextAlign, TextBaseline, ImageSmoothingQuality,
 };
-pub use path::{Path2D, PathCommand};
+pub use path::{Path2D, PathCommand, Polyline};
+pub use raster::{FillRule, Mask};
+pub use boolean::{PathOp, Region};
 pub use transforms::TransformMatrix;
-pub use compositing::{CompositeOperation, BlendMode, blend_colors};
+pub use compositing::{CompositeOperation, BlendMode, blend_colors, composite};
 pub use image_data::{ImageData, ColorSpace};
-pub use offscreen::{OffscreenCanvas, ImageBitmap};
-pub use text::{TextMetrics, TextDrawing};
+pub use offscreen::{OffscreenCanvas, PlaceholderCanvas, ImageBitmap, ResizeQuality};
+pub use image_bitmap::{
+    ImageBitmapDecoder, ImageBitmapOptions, ImageBitmapSource, ImageFetcher,
+    PremultiplyAlpha, ImageOrientation,
+};
+pub use text::{TextMetrics, TextDrawing, CanvasFont, CanvasFonts};
 pub use drawing::{CanvasImageSource, ImageDrawing};
 pub use webgl::{WebGLRenderingContext, WebGLProgram, WebGLShader, WebGLBuffer, WebGLTexture};
 
@@ -44,4 +58,10 @@ pub enum CanvasError {
     
     #[error("Not supported: {0}")]
     NotSupported(String),
+    
+    #[error("Index size: {0}")]
+    IndexSize(String),
+    
+    #[error("Out of range: {0}")]
+    Range(String),
 }
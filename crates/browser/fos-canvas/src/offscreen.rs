@@ -1,6 +1,11 @@
 //! OffscreenCanvas
 //!
-//! Canvas that can be used in workers.
+//! Canvas that can be used in workers. A `<canvas>` element can hand its
+//! rendering to an OffscreenCanvas with `transferControlToOffscreen()`; the
+//! OffscreenCanvas then moves to a worker thread and `commit()`s frames,
+//! which the element's placeholder picks up for compositing.
+
+use std::sync::{Arc, Mutex};
 
 use crate::context2d::CanvasRenderingContext2D;
 
@@ -10,6 +15,39 @@ pub struct OffscreenCanvas {
     width: u32,
     height: u32,
     context: Option<OffscreenContext>,
+    /// Frame slot shared with the placeholder `<canvas>`, if transferred
+    placeholder: Option<Arc<Mutex<FrameSlot>>>,
+}
+
+/// Latest frame committed to a placeholder
+#[derive(Debug, Default)]
+struct FrameSlot {
+    frame: Option<ImageBitmap>,
+    /// Committed since the placeholder last took it
+    fresh: bool,
+}
+
+/// Main-thread side of a `<canvas>` whose control was transferred
+#[derive(Debug, Clone)]
+pub struct PlaceholderCanvas {
+    slot: Arc<Mutex<FrameSlot>>,
+}
+
+impl PlaceholderCanvas {
+    /// The frame committed since the last call, if any
+    pub fn take_frame(&self) -> Option<ImageBitmap> {
+        let mut slot = self.slot.lock().unwrap();
+        if !slot.fresh {
+            return None;
+        }
+        slot.fresh = false;
+        slot.frame.clone()
+    }
+    
+    /// The most recently committed frame
+    pub fn current_frame(&self) -> Option<ImageBitmap> {
+        self.slot.lock().unwrap().frame.clone()
+    }
 }
 
 /// Offscreen context type
@@ -31,7 +69,7 @@ pub struct ImageBitmap {
 }
 
 /// Color space conversion
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum ColorSpaceConversion {
     #[default]
     Default,
@@ -39,7 +77,7 @@ pub enum ColorSpaceConversion {
 }
 
 /// Resize quality
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum ResizeQuality {
     Pixelated,
     Low,
@@ -48,6 +86,29 @@ pub enum ResizeQuality {
     High,
 }
 
+impl ResizeQuality {
+    /// Parse from string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "pixelated" => Some(Self::Pixelated),
+            "low" => Some(Self::Low),
+            "medium" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+    
+    /// Convert to string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pixelated => "pixelated",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
 impl OffscreenCanvas {
     /// Create new offscreen canvas
     pub fn new(width: u32, height: u32) -> Self {
@@ -55,6 +116,30 @@ impl OffscreenCanvas {
             width,
             height,
             context: None,
+            placeholder: None,
+        }
+    }
+    
+    /// `transferControlToOffscreen()`: an OffscreenCanvas rendering into a
+    /// `<canvas>` of the given size, and the element's placeholder
+    pub fn transfer_control(width: u32, height: u32) -> (Self, PlaceholderCanvas) {
+        let slot = Arc::new(Mutex::new(FrameSlot::default()));
+        let mut canvas = Self::new(width, height);
+        canvas.placeholder = Some(slot.clone());
+        (canvas, PlaceholderCanvas { slot })
+    }
+    
+    /// Whether this canvas renders into a placeholder `<canvas>`
+    pub fn has_placeholder(&self) -> bool {
+        self.placeholder.is_some()
+    }
+    
+    /// Push the current bitmap to the placeholder; no-op otherwise
+    pub fn commit(&self) {
+        if let Some(slot) = &self.placeholder {
+            let mut slot = slot.lock().unwrap();
+            slot.frame = Some(self.snapshot());
+            slot.fresh = true;
         }
     }
     
@@ -68,16 +153,22 @@ impl OffscreenCanvas {
         self.height
     }
     
-    /// Set width
+    /// Set width, resetting the bitmap
     pub fn set_width(&mut self, width: u32) {
         self.width = width;
-        // Would recreate context
+        self.reset_context();
     }
     
-    /// Set height
+    /// Set height, resetting the bitmap
     pub fn set_height(&mut self, height: u32) {
         self.height = height;
-        // Would recreate context
+        self.reset_context();
+    }
+    
+    fn reset_context(&mut self) {
+        if let Some(OffscreenContext::Canvas2D(ctx)) = &mut self.context {
+            *ctx = CanvasRenderingContext2D::new(self.width, self.height);
+        }
     }
     
     /// Get 2D context
@@ -96,19 +187,15 @@ impl OffscreenCanvas {
     
     /// Transfer to ImageBitmap
     pub fn transfer_to_image_bitmap(&self) -> ImageBitmap {
+        self.snapshot()
+    }
+    
+    fn snapshot(&self) -> ImageBitmap {
         let data = match &self.context {
             Some(OffscreenContext::Canvas2D(ctx)) => ctx.data().to_vec(),
             None => vec![0u8; (self.width * self.height * 4) as usize],
         };
-        
-        ImageBitmap {
-            data,
-            width: self.width,
-            height: self.height,
-            premultiplied_alpha: false,
-            color_space_conversion: ColorSpaceConversion::default(),
-            resize_quality: ResizeQuality::default(),
-        }
+        ImageBitmap::from_data(data, self.width, self.height, false)
     }
     
     /// Convert to blob
@@ -126,6 +213,23 @@ pub struct BlobOptions {
 }
 
 impl ImageBitmap {
+    /// Create from RGBA pixels, premultiplied by alpha or not
+    pub fn from_data(data: Vec<u8>, width: u32, height: u32, premultiplied_alpha: bool) -> Self {
+        Self {
+            data,
+            width,
+            height,
+            premultiplied_alpha,
+            color_space_conversion: ColorSpaceConversion::default(),
+            resize_quality: ResizeQuality::default(),
+        }
+    }
+    
+    pub(crate) fn with_resize_quality(mut self, quality: ResizeQuality) -> Self {
+        self.resize_quality = quality;
+        self
+    }
+    
     /// Get width
     pub fn width(&self) -> u32 {
         self.width
@@ -136,11 +240,21 @@ impl ImageBitmap {
         self.height
     }
     
+    /// Quality used when the bitmap was resized
+    pub fn resize_quality(&self) -> ResizeQuality {
+        self.resize_quality
+    }
+    
     /// Get data
     pub fn data(&self) -> &[u8] {
         &self.data
     }
     
+    /// Whether the color channels are premultiplied by alpha
+    pub fn premultiplied_alpha(&self) -> bool {
+        self.premultiplied_alpha
+    }
+    
     /// Close and release resources
     pub fn close(self) {
         // Resources dropped
@@ -168,4 +282,23 @@ mod tests {
         assert_eq!(bitmap.width(), 10);
         assert_eq!(bitmap.height(), 10);
     }
+    
+    #[test]
+    fn test_commit_from_worker() {
+        let (mut canvas, placeholder) = OffscreenCanvas::transfer_control(4, 4);
+        assert!(canvas.has_placeholder());
+        assert!(placeholder.take_frame().is_none());
+        
+        let worker = std::thread::spawn(move || {
+            canvas.get_context_2d().unwrap().fill_rect(0.0, 0.0, 2.0, 2.0);
+            canvas.commit();
+        });
+        worker.join().unwrap();
+        
+        let frame = placeholder.take_frame().unwrap();
+        assert_eq!(&frame.data()[..4], &[0, 0, 0, 255]);
+        // Each commit is taken once; the last one stays current
+        assert!(placeholder.take_frame().is_none());
+        assert_eq!(placeholder.current_frame().unwrap().width(), 4);
+    }
 }
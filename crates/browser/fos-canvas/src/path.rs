@@ -1,6 +1,14 @@
 //! Path2D
 //!
-//! Path construction for Canvas 2D.
+//! Path construction for Canvas 2D, and flattening of paths into polylines
+//! for rasterization.
+
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::boolean::{self, PathOp};
+use crate::raster::FillRule;
+use crate::transforms::TransformMatrix;
+use crate::CanvasError;
 
 /// 2D Path
 #[derive(Debug, Clone, Default)]
@@ -48,6 +56,7 @@ impl Path2D {
     
     /// Line to point
     pub fn line_to(&mut self, x: f64, y: f64) {
+        self.begin_subpath_at((x, y));
         self.commands.push(PathCommand::LineTo(x, y));
         self.current_x = x;
         self.current_y = y;
@@ -67,42 +76,111 @@ impl Path2D {
         self.current_y = y;
     }
     
-    /// Arc
-    pub fn arc(&mut self, x: f64, y: f64, radius: f64, start_angle: f64, end_angle: f64, counterclockwise: bool) {
+    /// Arc; a negative radius is an index size error
+    pub fn arc(&mut self, x: f64, y: f64, radius: f64, start_angle: f64, end_angle: f64, counterclockwise: bool) -> Result<(), CanvasError> {
+        if !finite(&[x, y, radius, start_angle, end_angle]) {
+            return Ok(());
+        }
+        if radius < 0.0 {
+            return Err(CanvasError::IndexSize(format!("negative arc radius {}", radius)));
+        }
+        self.begin_subpath_at(ellipse_point(x, y, radius, radius, 0.0, start_angle));
         self.commands.push(PathCommand::Arc { x, y, radius, start_angle, end_angle, counterclockwise });
-        // Update current position to end of arc
-        self.current_x = x + radius * end_angle.cos();
-        self.current_y = y + radius * end_angle.sin();
+        let end = start_angle + sweep(start_angle, end_angle, counterclockwise);
+        (self.current_x, self.current_y) = ellipse_point(x, y, radius, radius, 0.0, end);
+        Ok(())
     }
     
-    /// Arc to
-    pub fn arc_to(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, radius: f64) {
+    /// Arc to; a negative radius is an index size error
+    pub fn arc_to(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, radius: f64) -> Result<(), CanvasError> {
+        if !finite(&[x1, y1, x2, y2, radius]) {
+            return Ok(());
+        }
+        if radius < 0.0 {
+            return Err(CanvasError::IndexSize(format!("negative arc radius {}", radius)));
+        }
+        if self.commands.is_empty() {
+            self.move_to(x1, y1);
+        }
+        let from = (self.current_x, self.current_y);
         self.commands.push(PathCommand::ArcTo { x1, y1, x2, y2, radius });
+        (self.current_x, self.current_y) = match ArcToGeometry::new(from, (x1, y1), (x2, y2), radius) {
+            Some(arc) => arc.end(),
+            None => (x1, y1),
+        };
+        Ok(())
     }
     
-    /// Ellipse
-    pub fn ellipse(&mut self, x: f64, y: f64, rx: f64, ry: f64, rotation: f64, start_angle: f64, end_angle: f64, counterclockwise: bool) {
+    /// Ellipse; a negative radius is an index size error
+    #[allow(clippy::too_many_arguments)]
+    pub fn ellipse(&mut self, x: f64, y: f64, rx: f64, ry: f64, rotation: f64, start_angle: f64, end_angle: f64, counterclockwise: bool) -> Result<(), CanvasError> {
+        if !finite(&[x, y, rx, ry, rotation, start_angle, end_angle]) {
+            return Ok(());
+        }
+        if rx < 0.0 || ry < 0.0 {
+            return Err(CanvasError::IndexSize(format!("negative ellipse radius {}, {}", rx, ry)));
+        }
+        self.begin_subpath_at(ellipse_point(x, y, rx, ry, rotation, start_angle));
         self.commands.push(PathCommand::Ellipse { x, y, rx, ry, rotation, start_angle, end_angle, counterclockwise });
+        let end = start_angle + sweep(start_angle, end_angle, counterclockwise);
+        (self.current_x, self.current_y) = ellipse_point(x, y, rx, ry, rotation, end);
+        Ok(())
     }
     
     /// Rectangle
     pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
         self.commands.push(PathCommand::Rect { x, y, width, height });
+        (self.current_x, self.current_y) = (x, y);
+        (self.start_x, self.start_y) = (x, y);
     }
     
-    /// Round rectangle
-    pub fn round_rect(&mut self, x: f64, y: f64, width: f64, height: f64, radii: f64) {
-        // Construct rounded rect from arcs and lines
-        self.move_to(x + radii, y);
-        self.line_to(x + width - radii, y);
-        self.arc_to(x + width, y, x + width, y + radii, radii);
-        self.line_to(x + width, y + height - radii);
-        self.arc_to(x + width, y + height, x + width - radii, y + height, radii);
-        self.line_to(x + radii, y + height);
-        self.arc_to(x, y + height, x, y + height - radii, radii);
-        self.line_to(x, y + radii);
-        self.arc_to(x, y, x + radii, y, radii);
+    /// `roundRect()`: `radii` holds 1 to 4 corner radii, expanded like the
+    /// CSS `border-radius` shorthand starting at the corner at `(x, y)`.
+    /// Radii that would overlap are scaled down together
+    pub fn round_rect(&mut self, x: f64, y: f64, width: f64, height: f64, radii: &[f64]) -> Result<(), CanvasError> {
+        if !finite(&[x, y, width, height]) || !finite(radii) {
+            return Ok(());
+        }
+        let [tl, tr, br, bl] = match *radii {
+            [r] => [r, r, r, r],
+            [a, b] => [a, b, a, b],
+            [a, b, c] => [a, b, c, b],
+            [a, b, c, d] => [a, b, c, d],
+            _ => return Err(CanvasError::Range(format!("{} radii given, expected 1 to 4", radii.len()))),
+        };
+        if radii.iter().any(|r| *r < 0.0) {
+            return Err(CanvasError::Range("negative corner radius".to_string()));
+        }
+        
+        // Build in a normalized box, then mirror it onto negative extents
+        let (sx, sy) = (width.signum(), height.signum());
+        let (w, h) = (width.abs(), height.abs());
+        let scale = [w / (tl + tr), h / (tr + br), w / (br + bl), h / (bl + tl)]
+            .into_iter()
+            .filter(|s| s.is_finite())
+            .fold(1.0f64, f64::min);
+        let [tl, tr, br, bl] = [tl, tr, br, bl].map(|r| r * scale);
+        let map = |px: f64, py: f64| (x + sx * px, y + sy * py);
+        let angle = |a: f64| {
+            let a = if sx < 0.0 { PI - a } else { a };
+            if sy < 0.0 { -a } else { a }
+        };
+        let ccw = (sx < 0.0) != (sy < 0.0);
+        let corners = [
+            (w - tr, tr, tr, -FRAC_PI_2),
+            (w - br, h - br, br, 0.0),
+            (bl, h - bl, bl, FRAC_PI_2),
+            (tl, tl, tl, PI),
+        ];
+        let (mx, my) = map(tl, 0.0);
+        self.move_to(mx, my);
+        for (cx, cy, r, start) in corners {
+            let (px, py) = map(cx, cy);
+            self.ellipse(px, py, r, r, 0.0, angle(start), angle(start + FRAC_PI_2), ccw)?;
+        }
         self.close_path();
+        self.move_to(x, y);
+        Ok(())
     }
     
     /// Close path
@@ -114,7 +192,91 @@ impl Path2D {
     
     /// Add another path
     pub fn add_path(&mut self, path: &Path2D) {
+        self.add_path_with_transform(path, &TransformMatrix::identity());
+    }
+    
+    /// `addPath(path, transform)`: add the subpaths of another path mapped
+    /// through a transform; ignored if the transform is not finite
+    pub fn add_path_with_transform(&mut self, path: &Path2D, transform: &TransformMatrix) {
+        let m = transform;
+        if path.is_empty() || !finite(&[m.a, m.b, m.c, m.d, m.e, m.f]) {
+            return;
+        }
+        // Paths that open with a subpath keep their exact arcs untransformed
+        let starts_subpath = matches!(path.commands[0], PathCommand::MoveTo(..) | PathCommand::Rect { .. });
+        let path = if m.is_identity() && starts_subpath { path.clone() } else { path.transformed(m) };
         self.commands.extend(path.commands.iter().cloned());
+        (self.current_x, self.current_y) = (path.current_x, path.current_y);
+        (self.start_x, self.start_y) = (path.start_x, path.start_y);
+    }
+    
+    /// This path mapped through a transform; arcs become cubic curves
+    pub fn transformed(&self, m: &TransformMatrix) -> Path2D {
+        let mut lowering = Lowering::default();
+        for command in &self.commands {
+            lowering.command(*command);
+        }
+        let p = |x: f64, y: f64| m.transform_point(x, y);
+        let mut path = Path2D::new();
+        for command in lowering.out {
+            match command {
+                PathCommand::MoveTo(x, y) => {
+                    let (x, y) = p(x, y);
+                    path.move_to(x, y);
+                }
+                PathCommand::LineTo(x, y) => {
+                    let (x, y) = p(x, y);
+                    path.line_to(x, y);
+                }
+                PathCommand::QuadraticCurveTo { cpx, cpy, x, y } => {
+                    let ((cpx, cpy), (x, y)) = (p(cpx, cpy), p(x, y));
+                    path.quadratic_curve_to(cpx, cpy, x, y);
+                }
+                PathCommand::BezierCurveTo { cp1x, cp1y, cp2x, cp2y, x, y } => {
+                    let ((cp1x, cp1y), (cp2x, cp2y), (x, y)) = (p(cp1x, cp1y), p(cp2x, cp2y), p(x, y));
+                    path.bezier_curve_to(cp1x, cp1y, cp2x, cp2y, x, y);
+                }
+                PathCommand::ClosePath => path.close_path(),
+                _ => {}
+            }
+        }
+        path
+    }
+    
+    /// Closed path with one subpath per polygon
+    pub fn from_polygons(polygons: &[Vec<(f64, f64)>]) -> Path2D {
+        let mut path = Path2D::new();
+        for polygon in polygons {
+            let Some(&(x, y)) = polygon.first() else { continue };
+            path.move_to(x, y);
+            for &(x, y) in &polygon[1..] {
+                path.line_to(x, y);
+            }
+            path.close_path();
+        }
+        path
+    }
+    
+    /// Non-overlapping subpaths covering the area this path fills under
+    /// `rule`, so the result fills the same under either rule
+    pub fn simplify(&self, rule: FillRule, tolerance: f64) -> Path2D {
+        Path2D::from_polygons(&boolean::simplify(&self.polygons(tolerance), rule))
+    }
+    
+    /// Combine the areas two paths fill
+    pub fn op(&self, rule: FillRule, other: &Path2D, other_rule: FillRule, op: PathOp, tolerance: f64) -> Path2D {
+        Path2D::from_polygons(&boolean::op(&self.polygons(tolerance), rule, &other.polygons(tolerance), other_rule, op))
+    }
+    
+    fn polygons(&self, tolerance: f64) -> Vec<Vec<(f64, f64)>> {
+        self.flatten(tolerance).into_iter().map(|l| l.points).collect()
+    }
+    
+    /// Start the first subpath at a point if there is none
+    fn begin_subpath_at(&mut self, (x, y): (f64, f64)) {
+        if self.commands.is_empty() {
+            (self.start_x, self.start_y) = (x, y);
+        }
     }
     
     /// Get commands
@@ -126,6 +288,321 @@ impl Path2D {
     pub fn is_empty(&self) -> bool {
         self.commands.is_empty()
     }
+    
+    /// Flatten into one polyline per subpath; curves are approximated to
+    /// within `tolerance` (in path units)
+    pub fn flatten(&self, tolerance: f64) -> Vec<Polyline> {
+        let mut flattener = Flattener {
+            tolerance: tolerance.max(1e-3),
+            done: Vec::new(),
+            current: Polyline::default(),
+        };
+        for command in &self.commands {
+            flattener.command(*command);
+        }
+        flattener.finish()
+    }
+}
+
+/// Flattened subpath
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<(f64, f64)>,
+    pub closed: bool,
+}
+
+struct Flattener {
+    tolerance: f64,
+    done: Vec<Polyline>,
+    current: Polyline,
+}
+
+impl Flattener {
+    fn last(&self) -> Option<(f64, f64)> {
+        self.current.points.last().copied()
+    }
+    
+    fn move_to(&mut self, x: f64, y: f64) {
+        let finished = std::mem::take(&mut self.current);
+        if finished.points.len() > 1 {
+            self.done.push(finished);
+        }
+        self.current.points.push((x, y));
+    }
+    
+    /// Line to a point, starting a subpath there if there is none
+    fn line_to(&mut self, x: f64, y: f64) {
+        if self.last() != Some((x, y)) {
+            self.current.points.push((x, y));
+        }
+    }
+    
+    fn close(&mut self) {
+        let Some(&start) = self.current.points.first() else { return };
+        self.current.closed = true;
+        self.move_to(start.0, start.1);
+    }
+    
+    fn finish(mut self) -> Vec<Polyline> {
+        self.move_to(0.0, 0.0);
+        self.done
+    }
+    
+    /// Segments needed for a curve whose control polygon is `len` long
+    fn curve_segments(&self, len: f64) -> usize {
+        ((len / self.tolerance).sqrt().ceil() as usize).clamp(1, 256)
+    }
+    
+    fn command(&mut self, command: PathCommand) {
+        match command {
+            PathCommand::MoveTo(x, y) => self.move_to(x, y),
+            PathCommand::LineTo(x, y) => self.line_to(x, y),
+            PathCommand::QuadraticCurveTo { cpx, cpy, x, y } => {
+                let (x0, y0) = self.last().unwrap_or((cpx, cpy));
+                self.line_to(x0, y0);
+                let n = self.curve_segments(distance(x0, y0, cpx, cpy) + distance(cpx, cpy, x, y));
+                for i in 1..=n {
+                    let t = i as f64 / n as f64;
+                    let mt = 1.0 - t;
+                    self.line_to(
+                        mt * mt * x0 + 2.0 * mt * t * cpx + t * t * x,
+                        mt * mt * y0 + 2.0 * mt * t * cpy + t * t * y,
+                    );
+                }
+            }
+            PathCommand::BezierCurveTo { cp1x, cp1y, cp2x, cp2y, x, y } => {
+                let (x0, y0) = self.last().unwrap_or((cp1x, cp1y));
+                self.line_to(x0, y0);
+                let len = distance(x0, y0, cp1x, cp1y) + distance(cp1x, cp1y, cp2x, cp2y) + distance(cp2x, cp2y, x, y);
+                let n = self.curve_segments(len);
+                for i in 1..=n {
+                    let t = i as f64 / n as f64;
+                    let mt = 1.0 - t;
+                    let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+                    self.line_to(a * x0 + b * cp1x + c * cp2x + d * x, a * y0 + b * cp1y + c * cp2y + d * y);
+                }
+            }
+            PathCommand::Arc { x, y, radius, start_angle, end_angle, counterclockwise } => {
+                self.ellipse(x, y, radius, radius, 0.0, start_angle, end_angle, counterclockwise);
+            }
+            PathCommand::Ellipse { x, y, rx, ry, rotation, start_angle, end_angle, counterclockwise } => {
+                self.ellipse(x, y, rx, ry, rotation, start_angle, end_angle, counterclockwise);
+            }
+            PathCommand::ArcTo { x1, y1, x2, y2, radius } => self.arc_to(x1, y1, x2, y2, radius),
+            PathCommand::Rect { x, y, width, height } => {
+                self.move_to(x, y);
+                self.line_to(x + width, y);
+                self.line_to(x + width, y + height);
+                self.line_to(x, y + height);
+                self.close();
+            }
+            PathCommand::ClosePath => self.close(),
+        }
+    }
+    
+    #[allow(clippy::too_many_arguments)]
+    fn ellipse(&mut self, cx: f64, cy: f64, rx: f64, ry: f64, rotation: f64, start: f64, end: f64, ccw: bool) {
+        let sweep = sweep(start, end, ccw);
+        // Chord error r * (1 - cos(step / 2)) stays within tolerance
+        let r = rx.abs().max(ry.abs());
+        let step = if r > self.tolerance { 2.0 * (1.0 - self.tolerance / r).acos() } else { PI / 2.0 };
+        let n = ((sweep.abs() / step).ceil() as usize).clamp(1, 1024);
+        let (sx, sy) = ellipse_point(cx, cy, rx, ry, rotation, start);
+        self.line_to(sx, sy);
+        for i in 1..=n {
+            let (px, py) = ellipse_point(cx, cy, rx, ry, rotation, start + sweep * i as f64 / n as f64);
+            self.line_to(px, py);
+        }
+    }
+    
+    fn arc_to(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, radius: f64) {
+        let Some(from) = self.last() else {
+            self.move_to(x1, y1);
+            return;
+        };
+        match ArcToGeometry::new(from, (x1, y1), (x2, y2), radius) {
+            Some(arc) => {
+                self.line_to(arc.start_point.0, arc.start_point.1);
+                self.ellipse(arc.cx, arc.cy, arc.radius, arc.radius, 0.0, arc.start, arc.start + arc.sweep, arc.sweep < 0.0);
+            }
+            None => self.line_to(x1, y1),
+        }
+    }
+}
+
+/// Lowers commands to lines and curves that survive any affine transform
+#[derive(Default)]
+struct Lowering {
+    out: Vec<PathCommand>,
+    current: Option<(f64, f64)>,
+    start: (f64, f64),
+}
+
+impl Lowering {
+    fn move_to(&mut self, x: f64, y: f64) {
+        self.out.push(PathCommand::MoveTo(x, y));
+        self.current = Some((x, y));
+        self.start = (x, y);
+    }
+    
+    fn line_to(&mut self, x: f64, y: f64) {
+        if self.current.is_none() {
+            self.move_to(x, y);
+        } else {
+            self.out.push(PathCommand::LineTo(x, y));
+            self.current = Some((x, y));
+        }
+    }
+    
+    /// Elliptical arc as cubics of at most a quarter turn each
+    #[allow(clippy::too_many_arguments)]
+    fn ellipse(&mut self, cx: f64, cy: f64, rx: f64, ry: f64, rotation: f64, start: f64, sweep: f64) {
+        let (x, y) = ellipse_point(cx, cy, rx, ry, rotation, start);
+        self.line_to(x, y);
+        let n = (sweep.abs() / FRAC_PI_2).ceil().max(1.0) as usize;
+        let step = sweep / n as f64;
+        let k = 4.0 / 3.0 * (step / 4.0).tan();
+        let (sin_r, cos_r) = rotation.sin_cos();
+        let tangent = |angle: f64| {
+            let (dx, dy) = (-rx * angle.sin(), ry * angle.cos());
+            (dx * cos_r - dy * sin_r, dx * sin_r + dy * cos_r)
+        };
+        for i in 0..n {
+            let (a0, a1) = (start + step * i as f64, start + step * (i + 1) as f64);
+            let (p0, p3) = (ellipse_point(cx, cy, rx, ry, rotation, a0), ellipse_point(cx, cy, rx, ry, rotation, a1));
+            let (d0, d1) = (tangent(a0), tangent(a1));
+            self.out.push(PathCommand::BezierCurveTo {
+                cp1x: p0.0 + k * d0.0,
+                cp1y: p0.1 + k * d0.1,
+                cp2x: p3.0 - k * d1.0,
+                cp2y: p3.1 - k * d1.1,
+                x: p3.0,
+                y: p3.1,
+            });
+            self.current = Some(p3);
+        }
+    }
+    
+    fn command(&mut self, command: PathCommand) {
+        match command {
+            PathCommand::MoveTo(x, y) => self.move_to(x, y),
+            PathCommand::LineTo(x, y) => self.line_to(x, y),
+            PathCommand::QuadraticCurveTo { cpx, cpy, x, y } => {
+                if self.current.is_none() {
+                    self.move_to(cpx, cpy);
+                }
+                self.out.push(command);
+                self.current = Some((x, y));
+            }
+            PathCommand::BezierCurveTo { cp1x, cp1y, x, y, .. } => {
+                if self.current.is_none() {
+                    self.move_to(cp1x, cp1y);
+                }
+                self.out.push(command);
+                self.current = Some((x, y));
+            }
+            PathCommand::Arc { x, y, radius, start_angle, end_angle, counterclockwise } => {
+                self.ellipse(x, y, radius, radius, 0.0, start_angle, sweep(start_angle, end_angle, counterclockwise));
+            }
+            PathCommand::Ellipse { x, y, rx, ry, rotation, start_angle, end_angle, counterclockwise } => {
+                self.ellipse(x, y, rx, ry, rotation, start_angle, sweep(start_angle, end_angle, counterclockwise));
+            }
+            PathCommand::ArcTo { x1, y1, x2, y2, radius } => {
+                let Some(from) = self.current else {
+                    self.move_to(x1, y1);
+                    return;
+                };
+                match ArcToGeometry::new(from, (x1, y1), (x2, y2), radius) {
+                    Some(arc) => {
+                        self.line_to(arc.start_point.0, arc.start_point.1);
+                        self.ellipse(arc.cx, arc.cy, arc.radius, arc.radius, 0.0, arc.start, arc.sweep);
+                    }
+                    None => self.line_to(x1, y1),
+                }
+            }
+            PathCommand::Rect { x, y, width, height } => {
+                self.move_to(x, y);
+                self.line_to(x + width, y);
+                self.line_to(x + width, y + height);
+                self.line_to(x, y + height);
+                self.command(PathCommand::ClosePath);
+            }
+            PathCommand::ClosePath => {
+                if self.current.is_some() {
+                    self.out.push(PathCommand::ClosePath);
+                    self.current = Some(self.start);
+                }
+            }
+        }
+    }
+}
+
+/// Circle arc joining two tangent lines for `arcTo()`
+struct ArcToGeometry {
+    /// Tangent point on the line from the current point
+    start_point: (f64, f64),
+    cx: f64,
+    cy: f64,
+    radius: f64,
+    start: f64,
+    sweep: f64,
+}
+
+impl ArcToGeometry {
+    /// `None` when `arcTo()` degenerates to a line to `p1`
+    fn new((x0, y0): (f64, f64), (x1, y1): (f64, f64), (x2, y2): (f64, f64), radius: f64) -> Option<Self> {
+        let (v1x, v1y) = (x0 - x1, y0 - y1);
+        let (v2x, v2y) = (x2 - x1, y2 - y1);
+        let (len1, len2) = ((v1x * v1x + v1y * v1y).sqrt(), (v2x * v2x + v2y * v2y).sqrt());
+        let cross = v1x * v2y - v1y * v2x;
+        if radius <= 0.0 || len1 < 1e-12 || len2 < 1e-12 || cross.abs() < 1e-12 * len1 * len2 {
+            return None;
+        }
+        let (u1x, u1y, u2x, u2y) = (v1x / len1, v1y / len1, v2x / len2, v2y / len2);
+        let theta = (u1x * u2x + u1y * u2y).clamp(-1.0, 1.0).acos();
+        let tangent = radius / (theta / 2.0).tan();
+        let (bx, by) = (u1x + u2x, u1y + u2y);
+        let blen = (bx * bx + by * by).sqrt();
+        let center_dist = radius / (theta / 2.0).sin();
+        let (cx, cy) = (x1 + bx / blen * center_dist, y1 + by / blen * center_dist);
+        let (t1x, t1y) = (x1 + u1x * tangent, y1 + u1y * tangent);
+        let (t2x, t2y) = (x1 + u2x * tangent, y1 + u2y * tangent);
+        let start = (t1y - cy).atan2(t1x - cx);
+        let end = (t2y - cy).atan2(t2x - cx);
+        let sweep = (end - start + PI).rem_euclid(TAU) - PI;
+        Some(Self { start_point: (t1x, t1y), cx, cy, radius, start, sweep })
+    }
+    
+    fn end(&self) -> (f64, f64) {
+        ellipse_point(self.cx, self.cy, self.radius, self.radius, 0.0, self.start + self.sweep)
+    }
+}
+
+/// Signed angle an arc covers, at most one full turn
+fn sweep(start: f64, end: f64, ccw: bool) -> f64 {
+    if !ccw && end - start >= TAU {
+        TAU
+    } else if ccw && start - end >= TAU {
+        -TAU
+    } else if ccw {
+        -(start - end).rem_euclid(TAU)
+    } else {
+        (end - start).rem_euclid(TAU)
+    }
+}
+
+fn ellipse_point(cx: f64, cy: f64, rx: f64, ry: f64, rotation: f64, angle: f64) -> (f64, f64) {
+    let (sin_r, cos_r) = rotation.sin_cos();
+    let (ex, ey) = (rx * angle.cos(), ry * angle.sin());
+    (cx + ex * cos_r - ey * sin_r, cy + ex * sin_r + ey * cos_r)
+}
+
+fn finite(values: &[f64]) -> bool {
+    values.iter().all(|v| v.is_finite())
+}
+
+fn distance(x0: f64, y0: f64, x1: f64, y1: f64) -> f64 {
+    ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt()
 }
 
 #[cfg(test)]
@@ -150,4 +627,59 @@ mod tests {
         
         assert!(!path.is_empty());
     }
+    
+    #[test]
+    fn test_flatten() {
+        let mut path = Path2D::new();
+        path.rect(0.0, 0.0, 10.0, 10.0);
+        path.move_to(20.0, 0.0);
+        path.arc(20.0, 20.0, 20.0, -PI / 2.0, 0.0, false).unwrap();
+        
+        let lines = path.flatten(0.1);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].closed);
+        assert_eq!(lines[0].points, vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        // Quarter circle ends on the x axis through the center
+        let &(x, y) = lines[1].points.last().unwrap();
+        assert!((x - 40.0).abs() < 1e-9 && (y - 20.0).abs() < 1e-9);
+        assert!(lines[1].points.iter().all(|&(px, py)| (distance(px, py, 20.0, 20.0) - 20.0).abs() < 1e-9));
+    }
+    
+    #[test]
+    fn test_add_path_with_transform() {
+        let mut circle = Path2D::new();
+        circle.arc(0.0, 0.0, 10.0, 0.0, TAU, false).unwrap();
+        assert!(circle.arc(0.0, 0.0, -1.0, 0.0, PI, false).is_err());
+        
+        let mut path = Path2D::new();
+        path.add_path_with_transform(&circle, &TransformMatrix::new(2.0, 0.0, 0.0, 1.0, 50.0, 20.0));
+        let lines = path.flatten(0.01);
+        assert_eq!(lines.len(), 1);
+        // Cubic arcs stay on the stretched ellipse
+        for &(x, y) in &lines[0].points {
+            let (ex, ey) = ((x - 50.0) / 20.0, (y - 20.0) / 10.0);
+            assert!((ex * ex + ey * ey - 1.0).abs() < 1e-3);
+        }
+    }
+    
+    #[test]
+    fn test_round_rect_and_simplify() {
+        let mut path = Path2D::new();
+        assert!(path.round_rect(0.0, 0.0, 10.0, 10.0, &[1.0; 5]).is_err());
+        // Radii larger than the box are scaled to fit
+        path.round_rect(0.0, 0.0, 20.0, 20.0, &[20.0]).unwrap();
+        let lines = path.flatten(0.01);
+        assert!(lines[0].points.iter().all(|&(x, y)| (distance(x, y, 10.0, 10.0) - 10.0).abs() < 1e-6));
+        
+        // Overlapping squares simplify to disjoint pieces of the union
+        let mut squares = Path2D::new();
+        squares.rect(0.0, 0.0, 10.0, 10.0);
+        squares.rect(5.0, 5.0, 10.0, 10.0);
+        let simple = squares.simplify(FillRule::NonZero, 0.1);
+        let polygons: Vec<_> = simple.flatten(0.1).into_iter().map(|l| l.points).collect();
+        let plain = crate::raster::contains(&polygons, 7.0, 7.0, FillRule::EvenOdd);
+        assert!(plain);
+        let hole = squares.op(FillRule::NonZero, &squares, FillRule::NonZero, PathOp::Difference, 0.1);
+        assert!(hole.is_empty());
+    }
 }
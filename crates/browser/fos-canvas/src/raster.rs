@@ -0,0 +1,344 @@
+//! Rasterization
+//!
+//! Scanline polygon coverage for Canvas 2D fills, strokes, clips and shadows.
+//! Each pixel row is sampled on a few sub-scanlines with exact horizontal
+//! span coverage, giving anti-aliased edges.
+
+/// Canvas fill rule
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FillRule {
+    #[default]
+    NonZero,
+    EvenOdd,
+}
+
+impl FillRule {
+    /// Parse from string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "nonzero" => Some(Self::NonZero),
+            "evenodd" => Some(Self::EvenOdd),
+            _ => None,
+        }
+    }
+
+    /// Convert to string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NonZero => "nonzero",
+            Self::EvenOdd => "evenodd",
+        }
+    }
+
+    pub(crate) fn inside(self, winding: i32) -> bool {
+        match self {
+            Self::NonZero => winding != 0,
+            Self::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+/// Sub-scanlines per pixel row
+const SUBSAMPLES: usize = 4;
+
+/// Polygon edge with `y0 < y1`; `dir` is +1 for downward edges
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+    dir: i32,
+}
+
+impl Edge {
+    fn x_at(&self, y: f64) -> f64 {
+        self.x0 + (y - self.y0) * (self.x1 - self.x0) / (self.y1 - self.y0)
+    }
+}
+
+/// Edges of implicitly closed polygons
+fn edges(polygons: &[Vec<(f64, f64)>]) -> Vec<Edge> {
+    let mut edges = Vec::new();
+    for polygon in polygons {
+        for (i, &(xa, ya)) in polygon.iter().enumerate() {
+            let (xb, yb) = polygon[(i + 1) % polygon.len()];
+            if ya == yb || !(xa.is_finite() && ya.is_finite() && xb.is_finite() && yb.is_finite()) {
+                continue;
+            }
+            edges.push(if ya < yb {
+                Edge { x0: xa, y0: ya, x1: xb, y1: yb, dir: 1 }
+            } else {
+                Edge { x0: xb, y0: yb, x1: xa, y1: ya, dir: -1 }
+            });
+        }
+    }
+    edges
+}
+
+/// Whether a point is inside the polygons; points on an edge count as
+/// inside, as `isPointInPath()` requires
+pub fn contains(polygons: &[Vec<(f64, f64)>], x: f64, y: f64, rule: FillRule) -> bool {
+    if on_boundary(polygons, x, y) {
+        return true;
+    }
+    let winding = edges(polygons)
+        .iter()
+        .filter(|e| e.y0 <= y && y < e.y1 && e.x_at(y) > x)
+        .map(|e| e.dir)
+        .sum();
+    rule.inside(winding)
+}
+
+/// Whether a point lies on a polygon edge
+fn on_boundary(polygons: &[Vec<(f64, f64)>], x: f64, y: f64) -> bool {
+    polygons.iter().any(|polygon| {
+        (0..polygon.len()).any(|i| {
+            let ((xa, ya), (xb, yb)) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            let (dx, dy) = (xb - xa, yb - ya);
+            let len2 = dx * dx + dy * dy;
+            let t = if len2 > 0.0 { (((x - xa) * dx + (y - ya) * dy) / len2).clamp(0.0, 1.0) } else { 0.0 };
+            let (px, py) = (xa + t * dx - x, ya + t * dy - y);
+            px * px + py * py <= 1e-18
+        })
+    })
+}
+
+/// Per-pixel coverage in 0..=1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mask {
+    width: u32,
+    height: u32,
+    data: Vec<f32>,
+    /// Pixel rectangle `(x0, y0, x1, y1)` outside which coverage is zero
+    bounds: (u32, u32, u32, u32),
+}
+
+impl Mask {
+    /// Empty mask
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0.0; (width * height) as usize],
+            bounds: (0, 0, 0, 0),
+        }
+    }
+
+    /// Rasterize polygons (in pixel coordinates)
+    pub fn fill(polygons: &[Vec<(f64, f64)>], width: u32, height: u32, rule: FillRule) -> Self {
+        let mut mask = Self::new(width, height);
+        let edges = edges(polygons);
+        if edges.is_empty() || width == 0 || height == 0 {
+            return mask;
+        }
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+        for e in &edges {
+            min_x = min_x.min(e.x0.min(e.x1));
+            max_x = max_x.max(e.x0.max(e.x1));
+            min_y = min_y.min(e.y0);
+            max_y = max_y.max(e.y1);
+        }
+        let x0 = min_x.floor().clamp(0.0, width as f64) as u32;
+        let x1 = max_x.ceil().clamp(0.0, width as f64) as u32;
+        let y0 = min_y.floor().clamp(0.0, height as f64) as u32;
+        let y1 = max_y.ceil().clamp(0.0, height as f64) as u32;
+        if x0 >= x1 || y0 >= y1 {
+            return mask;
+        }
+        mask.bounds = (x0, y0, x1, y1);
+
+        let weight = 1.0 / SUBSAMPLES as f64;
+        let mut row = vec![0.0f64; width as usize];
+        let mut crossings: Vec<(f64, i32)> = Vec::new();
+        for py in y0..y1 {
+            row[x0 as usize..x1 as usize].fill(0.0);
+            for s in 0..SUBSAMPLES {
+                let sy = py as f64 + (s as f64 + 0.5) * weight;
+                crossings.clear();
+                crossings.extend(edges.iter().filter(|e| e.y0 <= sy && sy < e.y1).map(|e| (e.x_at(sy), e.dir)));
+                crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let mut winding = 0;
+                for pair in crossings.windows(2) {
+                    winding += pair[0].1;
+                    if rule.inside(winding) {
+                        add_span(&mut row, pair[0].0, pair[1].0, weight);
+                    }
+                }
+            }
+            let start = (py * width) as usize;
+            for x in x0..x1 {
+                mask.data[start + x as usize] = row[x as usize].min(1.0) as f32;
+            }
+        }
+        mask
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Coverage at a pixel (zero outside the mask)
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        if x >= self.width || y >= self.height {
+            return 0.0;
+        }
+        self.data[(y * self.width + x) as usize]
+    }
+
+    /// Pixel rectangle `(x0, y0, x1, y1)` containing all coverage
+    pub fn bounds(&self) -> (u32, u32, u32, u32) {
+        self.bounds
+    }
+
+    /// Scale all coverage by a factor
+    pub fn scale(&mut self, factor: f32) {
+        for c in &mut self.data {
+            *c *= factor;
+        }
+    }
+
+    /// Scale the coverage of each pixel within the bounds by `f(x, y)`
+    pub fn modulate(&mut self, mut f: impl FnMut(u32, u32) -> f32) {
+        let (x0, y0, x1, y1) = self.bounds;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let idx = (y * self.width + x) as usize;
+                if self.data[idx] > 0.0 {
+                    self.data[idx] *= f(x, y);
+                }
+            }
+        }
+    }
+
+    /// Intersect with another mask of the same size
+    pub fn intersect(&mut self, other: &Mask) {
+        for (c, o) in self.data.iter_mut().zip(&other.data) {
+            *c *= o;
+        }
+        let (a, b) = (self.bounds, other.bounds);
+        self.bounds = (a.0.max(b.0), a.1.max(b.1), a.2.min(b.2), a.3.min(b.3));
+        if self.bounds.0 >= self.bounds.2 || self.bounds.1 >= self.bounds.3 {
+            self.bounds = (0, 0, 0, 0);
+        }
+    }
+
+    /// Approximate a Gaussian blur with standard deviation `sigma` by three
+    /// box blurs per axis
+    pub fn blur(&mut self, sigma: f64) {
+        if sigma <= 0.0 || self.bounds.0 >= self.bounds.2 {
+            return;
+        }
+        let radius = (((12.0 * sigma * sigma / 3.0 + 1.0).sqrt() - 1.0) / 2.0).round().max(1.0) as u32;
+        let grow = radius * 3;
+        let (x0, y0, x1, y1) = self.bounds;
+        self.bounds = (
+            x0.saturating_sub(grow),
+            y0.saturating_sub(grow),
+            (x1 + grow).min(self.width),
+            (y1 + grow).min(self.height),
+        );
+        let (x0, y0, x1, y1) = self.bounds;
+        let w = self.width as usize;
+        let mut line = Vec::new();
+        for _ in 0..3 {
+            for y in y0..y1 {
+                let start = y as usize * w;
+                box_blur(&mut self.data, start + x0 as usize, 1, (x1 - x0) as usize, radius as usize, &mut line);
+            }
+            for x in x0..x1 {
+                let start = y0 as usize * w + x as usize;
+                box_blur(&mut self.data, start, w, (y1 - y0) as usize, radius as usize, &mut line);
+            }
+        }
+    }
+}
+
+/// Add `weight` times the horizontal coverage of `[xa, xb)` to a row
+fn add_span(row: &mut [f64], xa: f64, xb: f64, weight: f64) {
+    let width = row.len() as f64;
+    let (xa, xb) = (xa.clamp(0.0, width), xb.clamp(0.0, width));
+    if xb <= xa {
+        return;
+    }
+    let (ia, ib) = (xa.floor() as usize, xb.floor() as usize);
+    if ia == ib {
+        row[ia] += (xb - xa) * weight;
+        return;
+    }
+    row[ia] += (ia as f64 + 1.0 - xa) * weight;
+    for c in &mut row[ia + 1..ib] {
+        *c += weight;
+    }
+    if ib < row.len() {
+        row[ib] += (xb - ib as f64) * weight;
+    }
+}
+
+/// Box blur `len` samples starting at `start` spaced by `step`
+fn box_blur(data: &mut [f32], start: usize, step: usize, len: usize, radius: usize, line: &mut Vec<f32>) {
+    line.clear();
+    line.extend((0..len).map(|i| data[start + i * step]));
+    let window = (2 * radius + 1) as f32;
+    let mut sum: f32 = line.iter().take(radius + 1).sum();
+    for i in 0..len {
+        data[start + i * step] = sum / window;
+        if i + radius + 1 < len {
+            sum += line[i + radius + 1];
+        }
+        if i >= radius {
+            sum -= line[i - radius];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<(f64, f64)> {
+        vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size)]
+    }
+
+    #[test]
+    fn test_fill_coverage() {
+        let mask = Mask::fill(&[square(2.0, 2.0, 4.0)], 10, 10, FillRule::NonZero);
+        assert_eq!(mask.get(3, 3), 1.0);
+        assert_eq!(mask.get(1, 3), 0.0);
+        assert_eq!(mask.bounds(), (2, 2, 6, 6));
+
+        // Half-covered pixels along a fractional edge
+        let mask = Mask::fill(&[square(2.5, 2.0, 4.0)], 10, 10, FillRule::NonZero);
+        assert!((mask.get(2, 3) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fill_rules() {
+        // Inner square wound the same way: a hole only under even-odd
+        let shapes = [square(0.0, 0.0, 8.0), square(2.0, 2.0, 4.0)];
+        assert_eq!(Mask::fill(&shapes, 8, 8, FillRule::NonZero).get(4, 4), 1.0);
+        assert_eq!(Mask::fill(&shapes, 8, 8, FillRule::EvenOdd).get(4, 4), 0.0);
+        assert!(contains(&shapes, 4.0, 4.0, FillRule::NonZero));
+        assert!(!contains(&shapes, 4.0, 4.0, FillRule::EvenOdd));
+        assert!(!contains(&shapes, 9.0, 4.0, FillRule::NonZero));
+        // Edges of the outer square and of the hole are inside
+        assert!(contains(&shapes, 8.0, 4.0, FillRule::NonZero));
+        assert!(contains(&shapes, 4.0, 8.0, FillRule::NonZero));
+        assert!(contains(&shapes, 6.0, 4.0, FillRule::EvenOdd));
+    }
+
+    #[test]
+    fn test_blur_spreads_coverage() {
+        let mut mask = Mask::fill(&[square(10.0, 10.0, 4.0)], 24, 24, FillRule::NonZero);
+        let total: f32 = mask.data.iter().sum();
+        mask.blur(2.0);
+        assert!(mask.get(8, 12) > 0.0);
+        assert!(mask.get(12, 12) < 1.0);
+        let blurred: f32 = mask.data.iter().sum();
+        assert!((blurred - total).abs() < 0.01);
+    }
+}
@@ -0,0 +1,229 @@
+//! Stroking
+//!
+//! Dash patterns and stroke outlines for Canvas 2D. A stroke becomes a set of
+//! positively wound polygons (segment bodies, joins and caps) whose nonzero
+//! union is the stroked area.
+
+use std::f64::consts::TAU;
+
+use crate::context2d::{LineCap, LineJoin};
+use crate::path::Polyline;
+
+/// Stroke geometry parameters
+#[derive(Debug, Clone, Copy)]
+pub struct StrokeParams {
+    pub width: f64,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    pub miter_limit: f64,
+}
+
+/// Split polylines into dashes; `pattern` must have an even length
+pub fn dash(polylines: &[Polyline], pattern: &[f64], offset: f64) -> Vec<Polyline> {
+    let total: f64 = pattern.iter().sum();
+    if pattern.is_empty() || total <= 0.0 || !total.is_finite() {
+        return polylines.to_vec();
+    }
+    let mut dashes = Vec::new();
+    for polyline in polylines {
+        let mut points = polyline.points.clone();
+        if polyline.closed {
+            points.push(points[0]);
+        }
+        // Find where the offset falls in the pattern
+        let mut index = 0;
+        let mut remaining = pattern[0];
+        let mut phase = offset.rem_euclid(total);
+        while phase >= remaining {
+            phase -= remaining;
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        }
+        remaining -= phase;
+
+        let mut current = Polyline::default();
+        if index % 2 == 0 {
+            current.points.push(points[0]);
+        }
+        for pair in points.windows(2) {
+            let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+            let len = ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt();
+            let mut t = 0.0;
+            while len - t > remaining {
+                t += remaining;
+                let point = (x0 + (x1 - x0) * t / len, y0 + (y1 - y0) * t / len);
+                if index % 2 == 0 {
+                    current.points.push(point);
+                    dashes.push(std::mem::take(&mut current));
+                } else {
+                    current.points.push(point);
+                }
+                index = (index + 1) % pattern.len();
+                remaining = pattern[index];
+            }
+            remaining -= len - t;
+            if index % 2 == 0 {
+                current.points.push((x1, y1));
+            }
+        }
+        if current.points.len() > 1 {
+            dashes.push(current);
+        }
+    }
+    dashes
+}
+
+/// Outline polygons covering the stroke of the polylines
+pub fn outline(polylines: &[Polyline], params: &StrokeParams) -> Vec<Vec<(f64, f64)>> {
+    let hw = params.width / 2.0;
+    let mut polygons = Vec::new();
+    if !(hw > 0.0 && hw.is_finite()) {
+        return polygons;
+    }
+    for polyline in polylines {
+        let mut points = polyline.points.clone();
+        points.dedup();
+        if polyline.closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() < 2 {
+            continue;
+        }
+        let n = points.len();
+        let segments = if polyline.closed { n } else { n - 1 };
+        for i in 0..segments {
+            let (p, q) = (points[i], points[(i + 1) % n]);
+            let (nx, ny) = normal(p, q, hw);
+            polygons.push(vec![(p.0 + nx, p.1 + ny), (q.0 + nx, q.1 + ny), (q.0 - nx, q.1 - ny), (p.0 - nx, p.1 - ny)]);
+        }
+        let joints = if polyline.closed { 0..n } else { 1..n - 1 };
+        for i in joints {
+            let prev = points[(i + n - 1) % n];
+            join(&mut polygons, prev, points[i], points[(i + 1) % n], hw, params);
+        }
+        if !polyline.closed {
+            cap(&mut polygons, points[1], points[0], hw, params.cap);
+            cap(&mut polygons, points[n - 2], points[n - 1], hw, params.cap);
+        }
+    }
+    for polygon in &mut polygons {
+        if signed_area(polygon) < 0.0 {
+            polygon.reverse();
+        }
+    }
+    polygons
+}
+
+/// Left-hand normal of `p -> q` scaled to `len`
+fn normal(p: (f64, f64), q: (f64, f64), len: f64) -> (f64, f64) {
+    let (dx, dy) = (q.0 - p.0, q.1 - p.1);
+    let d = (dx * dx + dy * dy).sqrt();
+    (-dy / d * len, dx / d * len)
+}
+
+fn join(polygons: &mut Vec<Vec<(f64, f64)>>, a: (f64, f64), v: (f64, f64), b: (f64, f64), hw: f64, params: &StrokeParams) {
+    let (d1x, d1y) = (v.0 - a.0, v.1 - a.1);
+    let (d2x, d2y) = (b.0 - v.0, b.1 - v.1);
+    let cross = d1x * d2y - d1y * d2x;
+    if cross.abs() < 1e-12 && d1x * d2x + d1y * d2y > 0.0 {
+        return;
+    }
+    if let LineJoin::Round = params.join {
+        polygons.push(circle(v, hw));
+        return;
+    }
+    // Offsets to the outside of the turn
+    let side = if cross > 0.0 { -1.0 } else { 1.0 };
+    let n1 = normal(a, v, hw);
+    let n2 = normal(v, b, hw);
+    let (o1, o2) = ((n1.0 * side, n1.1 * side), (n2.0 * side, n2.1 * side));
+    let outer1 = (v.0 + o1.0, v.1 + o1.1);
+    let outer2 = (v.0 + o2.0, v.1 + o2.1);
+    let m = ((o1.0 + o2.0) / 2.0, (o1.1 + o2.1) / 2.0);
+    let m_len2 = m.0 * m.0 + m.1 * m.1;
+    let miter = matches!(params.join, LineJoin::Miter)
+        && m_len2 > 1e-12
+        && hw / m_len2.sqrt() <= params.miter_limit;
+    if miter {
+        let scale = hw * hw / m_len2;
+        polygons.push(vec![v, outer1, (v.0 + m.0 * scale, v.1 + m.1 * scale), outer2]);
+    } else {
+        polygons.push(vec![v, outer1, outer2]);
+    }
+}
+
+/// Cap at `end`, for a segment arriving from `from`
+fn cap(polygons: &mut Vec<Vec<(f64, f64)>>, from: (f64, f64), end: (f64, f64), hw: f64, cap: LineCap) {
+    match cap {
+        LineCap::Butt => {}
+        LineCap::Round => polygons.push(circle(end, hw)),
+        LineCap::Square => {
+            let (nx, ny) = normal(from, end, hw);
+            // Direction of travel, rotated back from the normal
+            let (dx, dy) = (ny, -nx);
+            polygons.push(vec![
+                (end.0 + nx, end.1 + ny),
+                (end.0 + nx + dx, end.1 + ny + dy),
+                (end.0 - nx + dx, end.1 - ny + dy),
+                (end.0 - nx, end.1 - ny),
+            ]);
+        }
+    }
+}
+
+fn circle(center: (f64, f64), radius: f64) -> Vec<(f64, f64)> {
+    let n = ((radius * 4.0).ceil() as usize).clamp(8, 128);
+    (0..n)
+        .map(|i| {
+            let (sin, cos) = (TAU * i as f64 / n as f64).sin_cos();
+            (center.0 + radius * cos, center.1 + radius * sin)
+        })
+        .collect()
+}
+
+fn signed_area(polygon: &[(f64, f64)]) -> f64 {
+    let n = polygon.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raster::{contains, FillRule};
+
+    fn line(points: &[(f64, f64)]) -> Polyline {
+        Polyline { points: points.to_vec(), closed: false }
+    }
+
+    #[test]
+    fn test_dash() {
+        let dashes = dash(&[line(&[(0.0, 0.0), (10.0, 0.0)])], &[3.0, 2.0], 0.0);
+        let spans: Vec<_> = dashes.iter().map(|d| (d.points[0].0, d.points.last().unwrap().0)).collect();
+        assert_eq!(spans, vec![(0.0, 3.0), (5.0, 8.0)]);
+
+        // Offset shifts the pattern back along the line
+        let dashes = dash(&[line(&[(0.0, 0.0), (10.0, 0.0)])], &[3.0, 2.0], 4.0);
+        assert_eq!(dashes[0].points, vec![(1.0, 0.0), (4.0, 0.0)]);
+    }
+
+    #[test]
+    fn test_caps_and_joins() {
+        let params = StrokeParams { width: 2.0, cap: LineCap::Butt, join: LineJoin::Miter, miter_limit: 10.0 };
+        let corner = [line(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)])];
+        let polygons = outline(&corner, &params);
+        // Miter fills the outer corner; butt caps stop at the ends
+        assert!(contains(&polygons, 10.9, -0.9, FillRule::NonZero));
+        assert!(!contains(&polygons, -0.5, 0.0, FillRule::NonZero));
+
+        let params = StrokeParams { join: LineJoin::Bevel, cap: LineCap::Square, ..params };
+        let polygons = outline(&corner, &params);
+        assert!(!contains(&polygons, 10.9, -0.9, FillRule::NonZero));
+        assert!(contains(&polygons, -0.5, 0.0, FillRule::NonZero));
+    }
+}
@@ -1,6 +1,19 @@
 //! Text Drawing
 //!
-//! Canvas 2D text methods.
+//! Canvas 2D text methods. Text is shaped with fos-text and its glyph
+//! outlines go through the same path rasterizer as fills and strokes, so
+//! transforms, clipping, shadows and compositing apply to text as well.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use fos_text::font::{resolve_generic_family, GlyphId, OutlineBuilder};
+use fos_text::{FontDatabase, FontFace, FontId, FontMatch, FontQuery, FontStretch, FontStyle, FontWeight, TextShaper};
+
+use crate::context2d::{CanvasRenderingContext2D, TextAlign, TextBaseline};
+use crate::path::Path2D;
+use crate::raster::FillRule;
 
 /// Text metrics
 #[derive(Debug, Clone, Default)]
@@ -19,71 +32,614 @@ pub struct TextMetrics {
     pub ideographic_baseline: f64,
 }
 
+/// Parsed CSS `font` shorthand
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanvasFont {
+    pub style: FontStyle,
+    pub small_caps: bool,
+    /// Weight (1-1000)
+    pub weight: u16,
+    pub stretch: Option<&'static str>,
+    /// Size in CSS pixels
+    pub size: f64,
+    pub families: Vec<String>,
+}
+
+/// Default canvas font size; relative sizes resolve against it
+const DEFAULT_SIZE: f64 = 10.0;
+
+/// Horizontal shear of synthesized oblique faces (tan 14deg)
+const SYNTHETIC_OBLIQUE_SKEW: f64 = 0.25;
+
+/// Offset of the second outline copy drawn for synthesized bold, in ems
+const SYNTHETIC_BOLD_OFFSET: f64 = 1.0 / 24.0;
+
+const STRETCH_KEYWORDS: &[&str] = &[
+    "ultra-condensed", "extra-condensed", "condensed", "semi-condensed",
+    "semi-expanded", "expanded", "extra-expanded", "ultra-expanded",
+];
+
+const GENERIC_FAMILIES: &[&str] = &[
+    "serif", "sans-serif", "monospace", "cursive", "fantasy",
+    "system-ui", "ui-serif", "ui-sans-serif", "ui-monospace",
+];
+
+impl Default for CanvasFont {
+    fn default() -> Self {
+        Self {
+            style: FontStyle::Normal,
+            small_caps: false,
+            weight: 400,
+            stretch: None,
+            size: DEFAULT_SIZE,
+            families: vec!["sans-serif".to_string()],
+        }
+    }
+}
+
+impl CanvasFont {
+    /// Parse a CSS `font` value: `[style || variant || weight || stretch]?
+    /// size[/line-height]? family#`
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut font = Self { families: Vec::new(), ..Self::default() };
+        let (mut style, mut variant, mut weight, mut stretch) = (false, false, false, false);
+        let mut rest = s.trim();
+        loop {
+            let end = rest.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(rest.len());
+            let word = &rest[..end];
+            rest = rest[end..].trim_start();
+            if let Some(size) = parse_size(word) {
+                font.size = size;
+                break;
+            }
+            match word.to_ascii_lowercase().as_str() {
+                // `normal` may stand for any unset property
+                "normal" => {}
+                "italic" | "oblique" if !style => {
+                    font.style = if word.eq_ignore_ascii_case("italic") { FontStyle::Italic } else { FontStyle::Oblique };
+                    style = true;
+                }
+                "small-caps" if !variant => {
+                    font.small_caps = true;
+                    variant = true;
+                }
+                "bold" | "bolder" if !weight => {
+                    font.weight = 700;
+                    weight = true;
+                }
+                "lighter" if !weight => {
+                    font.weight = 100;
+                    weight = true;
+                }
+                w if !stretch && STRETCH_KEYWORDS.contains(&w) => {
+                    font.stretch = STRETCH_KEYWORDS.iter().find(|k| **k == w).copied();
+                    stretch = true;
+                }
+                w => match w.parse::<u16>() {
+                    Ok(n) if !weight && (1..=1000).contains(&n) => {
+                        font.weight = n;
+                        weight = true;
+                    }
+                    _ => return None,
+                },
+            }
+        }
+        // Line height is accepted and ignored
+        if let Some(after) = rest.strip_prefix('/') {
+            let after = after.trim_start();
+            let end = after.find(char::is_whitespace).unwrap_or(after.len());
+            if end == 0 {
+                return None;
+            }
+            rest = after[end..].trim_start();
+        }
+        font.families = parse_families(rest)?;
+        Some(font)
+    }
+}
+
+/// Serialized as the `font` getter returns it
+impl fmt::Display for CanvasFont {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.style {
+            FontStyle::Normal => {}
+            FontStyle::Italic => write!(f, "italic ")?,
+            FontStyle::Oblique => write!(f, "oblique ")?,
+        }
+        if self.small_caps {
+            write!(f, "small-caps ")?;
+        }
+        match self.weight {
+            400 => {}
+            700 => write!(f, "bold ")?,
+            w => write!(f, "{} ", w)?,
+        }
+        if let Some(stretch) = self.stretch {
+            write!(f, "{} ", stretch)?;
+        }
+        write!(f, "{}px", self.size)?;
+        for (i, family) in self.families.iter().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            // Quoted unless a single identifier
+            let plain = family.chars().next().is_some_and(|c| !c.is_ascii_digit())
+                && family.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+            if plain {
+                write!(f, "{}{}", sep, family)?;
+            } else {
+                write!(f, "{}\"{}\"", sep, family.replace('\\', "\\\\").replace('"', "\\\""))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Font size in pixels
+fn parse_size(word: &str) -> Option<f64> {
+    let keyword = match word.to_ascii_lowercase().as_str() {
+        "xx-small" => Some(9.0),
+        "x-small" => Some(10.0),
+        "small" => Some(13.0),
+        "medium" => Some(16.0),
+        "large" => Some(18.0),
+        "x-large" => Some(24.0),
+        "xx-large" => Some(32.0),
+        "xxx-large" => Some(48.0),
+        "larger" => Some(DEFAULT_SIZE * 1.2),
+        "smaller" => Some(DEFAULT_SIZE / 1.2),
+        _ => None,
+    };
+    if keyword.is_some() {
+        return keyword;
+    }
+    let split = word.find(|c: char| c.is_ascii_alphabetic() || c == '%')?;
+    let (number, unit) = word.split_at(split);
+    let value: f64 = number.parse().ok().filter(|v: &f64| v.is_finite() && *v >= 0.0)?;
+    let scale = match unit.to_ascii_lowercase().as_str() {
+        "px" => 1.0,
+        "pt" => 4.0 / 3.0,
+        "pc" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        "q" => 96.0 / 101.6,
+        "em" | "rem" => DEFAULT_SIZE,
+        "%" => DEFAULT_SIZE / 100.0,
+        _ => return None,
+    };
+    Some(value * scale)
+}
+
+/// Comma-separated family list; quoted names or identifier sequences
+fn parse_families(s: &str) -> Option<Vec<String>> {
+    let mut families = Vec::new();
+    let mut rest = s.trim();
+    while !rest.is_empty() {
+        let (name, after) = if let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            let end = rest[1..].find(quote)? + 1;
+            (rest[1..end].to_string(), rest[end + 1..].trim_start())
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            let words: Vec<&str> = rest[..end].split_whitespace().collect();
+            if words.is_empty() || words.iter().any(|w| w.starts_with(|c: char| c.is_ascii_digit())) {
+                return None;
+            }
+            (words.join(" "), &rest[end..])
+        };
+        families.push(name);
+        rest = match after.strip_prefix(',') {
+            Some(next) if !next.trim().is_empty() => next.trim_start(),
+            Some(_) => return None,
+            None if after.is_empty() => after,
+            None => return None,
+        };
+    }
+    (!families.is_empty()).then_some(families)
+}
+
+/// Fonts available to canvas text, with face data cached after first use
+pub struct CanvasFonts {
+    db: Arc<FontDatabase>,
+    faces: Arc<Mutex<HashMap<FontId, Arc<Vec<u8>>>>>,
+    /// Families a font may name, if not every installed one
+    families: Option<Vec<String>>,
+}
+
+impl fmt::Debug for CanvasFonts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CanvasFonts").field("fonts", &self.db.len()).finish()
+    }
+}
+
+impl CanvasFonts {
+    pub fn new(db: FontDatabase) -> Self {
+        Self { db: Arc::new(db), faces: Arc::new(Mutex::new(HashMap::new())), families: None }
+    }
+
+    /// The same fonts, with only `families` matched by name; generic
+    /// families still resolve
+    pub fn with_families(&self, families: Vec<String>) -> Self {
+        Self { db: self.db.clone(), faces: self.faces.clone(), families: Some(families) }
+    }
+
+    /// Whether a font may name `family`
+    fn allows(&self, family: &str) -> bool {
+        self.families.as_ref().is_none_or(|families| families.iter().any(|f| f.eq_ignore_ascii_case(family)))
+    }
+
+    /// Installed system fonts, loaded once per process
+    pub fn system() -> Arc<Self> {
+        static SYSTEM: OnceLock<Arc<CanvasFonts>> = OnceLock::new();
+        SYSTEM.get_or_init(|| Arc::new(Self::new(FontDatabase::with_system_fonts()))).clone()
+    }
+
+    pub fn database(&self) -> &FontDatabase {
+        &self.db
+    }
+
+    /// Best match for a font, trying its families and their generic fallbacks
+    fn select(&self, font: &CanvasFont) -> Option<FontMatch> {
+        let mut families = Vec::new();
+        for family in &font.families {
+            if GENERIC_FAMILIES.contains(&family.as_str()) {
+                families.push(family.clone());
+                families.extend(resolve_generic_family(family).iter().map(|f| f.to_string()));
+            } else if self.allows(family) {
+                families.push(family.clone());
+            }
+        }
+        // Keywords map to width classes 1-4 and 6-9
+        let stretch = font.stretch
+            .and_then(|s| STRETCH_KEYWORDS.iter().position(|k| *k == s))
+            .map_or(FontStretch::NORMAL, |i| FontStretch::from_width_class(if i < 4 { i as u16 + 1 } else { i as u16 + 2 }));
+        let query = FontQuery {
+            families,
+            weight: FontWeight::from(font.weight),
+            style: font.style,
+            stretch,
+            ..FontQuery::default()
+        };
+        self.db.match_font(&query)
+    }
+
+    fn face_data(&self, id: FontId) -> Option<(Arc<Vec<u8>>, u32)> {
+        let index = self.db.font(id)?.index;
+        let mut faces = self.faces.lock().unwrap();
+        if let Some(data) = faces.get(&id) {
+            return Some((data.clone(), index));
+        }
+        let data = Arc::new(self.db.with_face_data(id, |data, _| data.to_vec())?);
+        faces.insert(id, data.clone());
+        Some((data, index))
+    }
+}
+
+/// Shaped text, in pixels relative to the left end of the alphabetic baseline
+struct TextLayout {
+    data: Arc<Vec<u8>>,
+    index: u32,
+    /// Pixels per font unit
+    scale: f64,
+    /// Glyphs with their origin, y up
+    glyphs: Vec<(GlyphId, f64, f64)>,
+    metrics: TextMetrics,
+    /// Offset of the textAlign anchor from the left end
+    anchor: f64,
+    /// Synthesis for a missing bold or oblique face
+    synthetic_bold: bool,
+    skew: f64,
+    /// Height of the textBaseline above the alphabetic baseline
+    baseline: f64,
+}
+
+impl CanvasRenderingContext2D {
+    /// Shape text with the current font, textAlign and textBaseline
+    fn layout_text(&self, text: &str) -> Option<TextLayout> {
+        // Whitespace other than spaces renders as spaces
+        let text: String = text.chars().map(|c| if matches!(c, '\t' | '\n' | '\x0C' | '\r') { ' ' } else { c }).collect();
+        let state = self.state();
+        let font = CanvasFont::parse(&state.font).unwrap_or_default();
+        let fonts = self.fonts();
+        let selected = fonts.select(&font)?;
+        let (data, index) = fonts.face_data(selected.id)?;
+        let face = FontFace::parse(&data, index, FontId(0))?;
+        let run = TextShaper::new().shape_with_data(&data, index, &text, font.size as f32).ok()?;
+        let scale = font.size / face.units_per_em().max(1) as f64;
+
+        let glyphs: Vec<(GlyphId, f64, f64)> = run
+            .positioned_glyphs()
+            .map(|g| (GlyphId(g.glyph_id), g.x as f64, g.y as f64))
+            .collect();
+        let width = run.width() as f64;
+
+        // Ink extents, y up
+        let (mut left, mut right, mut top, mut bottom) = (f64::MAX, f64::MIN, f64::MIN, f64::MAX);
+        for &(glyph, x, y) in &glyphs {
+            if let Some(bbox) = face.glyph_bounding_box(glyph) {
+                left = left.min(x + bbox.x_min as f64 * scale);
+                right = right.max(x + bbox.x_max as f64 * scale);
+                bottom = bottom.min(y + bbox.y_min as f64 * scale);
+                top = top.max(y + bbox.y_max as f64 * scale);
+            }
+        }
+        if left > right {
+            (left, right, top, bottom) = (0.0, 0.0, 0.0, 0.0);
+        }
+
+        let ascent = face.ascender() as f64 * scale;
+        let descent = -(face.descender() as f64) * scale;
+        // The em box is the font size, split in the ascent:descent ratio
+        let em_ascent = if ascent + descent > 0.0 { font.size * ascent / (ascent + descent) } else { font.size * 0.8 };
+        let em_descent = font.size - em_ascent;
+        let hanging = ascent * 0.8;
+        let ideographic = -descent;
+
+        let baseline = match state.text_baseline {
+            TextBaseline::Top => em_ascent,
+            TextBaseline::Hanging => hanging,
+            TextBaseline::Middle => (em_ascent - em_descent) / 2.0,
+            TextBaseline::Alphabetic => 0.0,
+            TextBaseline::Ideographic => ideographic,
+            TextBaseline::Bottom => -em_descent,
+        };
+        // Left-to-right only: start is left, end is right
+        let anchor = match state.text_align {
+            TextAlign::Start | TextAlign::Left => 0.0,
+            TextAlign::End | TextAlign::Right => width,
+            TextAlign::Center => width / 2.0,
+        };
+
+        let metrics = TextMetrics {
+            width,
+            actual_bounding_box_left: anchor - left,
+            actual_bounding_box_right: right - anchor,
+            font_bounding_box_ascent: ascent - baseline,
+            font_bounding_box_descent: descent + baseline,
+            actual_bounding_box_ascent: top - baseline,
+            actual_bounding_box_descent: baseline - bottom,
+            em_height_ascent: em_ascent - baseline,
+            em_height_descent: em_descent + baseline,
+            hanging_baseline: hanging - baseline,
+            alphabetic_baseline: -baseline,
+            ideographic_baseline: ideographic - baseline,
+        };
+        Some(TextLayout {
+            data,
+            index,
+            scale,
+            glyphs,
+            metrics,
+            anchor,
+            baseline,
+            synthetic_bold: selected.synthetic_bold,
+            skew: if selected.synthetic_oblique { SYNTHETIC_OBLIQUE_SKEW } else { 0.0 },
+        })
+    }
+
+    /// Glyph outlines of text drawn at `(x, y)`, condensed to fit `max_width`
+    fn text_path(&self, text: &str, x: f64, y: f64, max_width: f64) -> Option<Path2D> {
+        if !(x.is_finite() && y.is_finite()) || max_width.is_nan() || max_width <= 0.0 {
+            return None;
+        }
+        let layout = self.layout_text(text)?;
+        let face = FontFace::parse(&layout.data, layout.index, FontId(0))?;
+        let width = layout.metrics.width;
+        let condense = if width > max_width { max_width / width } else { 1.0 };
+        let origin_x = x - layout.anchor * condense;
+        let origin_y = y + layout.baseline;
+
+        // Synthetic bold overlaps a second, shifted copy of each outline
+        let offsets: &[f64] = if layout.synthetic_bold { &[0.0, SYNTHETIC_BOLD_OFFSET] } else { &[0.0] };
+        let em = face.units_per_em().max(1) as f64 * layout.scale;
+        let mut path = Path2D::new();
+        for &(glyph, gx, gy) in &layout.glyphs {
+            for offset in offsets {
+                let mut outliner = GlyphOutliner {
+                    path: &mut path,
+                    x: origin_x + (gx + offset * em) * condense,
+                    y: origin_y - gy,
+                    scale_x: layout.scale * condense,
+                    scale_y: layout.scale,
+                    skew: layout.skew,
+                };
+                face.outline_glyph(glyph, &mut outliner);
+            }
+        }
+        Some(path)
+    }
+}
+
+/// Adds glyph outlines (font units, y up) to a path in canvas user space
+struct GlyphOutliner<'a> {
+    path: &'a mut Path2D,
+    x: f64,
+    y: f64,
+    scale_x: f64,
+    scale_y: f64,
+    /// Shear of x by y, for synthetic oblique
+    skew: f64,
+}
+
+impl GlyphOutliner<'_> {
+    fn map(&self, x: f32, y: f32) -> (f64, f64) {
+        let x = x as f64 + y as f64 * self.skew;
+        (self.x + x * self.scale_x, self.y - y as f64 * self.scale_y)
+    }
+}
+
+impl OutlineBuilder for GlyphOutliner<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.path.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.path.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let ((x1, y1), (x, y)) = (self.map(x1, y1), self.map(x, y));
+        self.path.quadratic_curve_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let ((x1, y1), (x2, y2), (x, y)) = (self.map(x1, y1), self.map(x2, y2), self.map(x, y));
+        self.path.bezier_curve_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.path.close_path();
+    }
+}
+
 /// Text drawing functions for CanvasRenderingContext2D
 pub trait TextDrawing {
     /// Fill text
     fn fill_text(&mut self, text: &str, x: f64, y: f64);
-    
+
     /// Fill text with max width
     fn fill_text_max(&mut self, text: &str, x: f64, y: f64, max_width: f64);
-    
+
     /// Stroke text
     fn stroke_text(&mut self, text: &str, x: f64, y: f64);
-    
-    /// Stroke text with max width 
+
+    /// Stroke text with max width
     fn stroke_text_max(&mut self, text: &str, x: f64, y: f64, max_width: f64);
-    
+
     /// Measure text
     fn measure_text(&self, text: &str) -> TextMetrics;
 }
 
-impl TextDrawing for super::context2d::CanvasRenderingContext2D {
+impl TextDrawing for CanvasRenderingContext2D {
     fn fill_text(&mut self, text: &str, x: f64, y: f64) {
         self.fill_text_max(text, x, y, f64::INFINITY);
     }
-    
-    fn fill_text_max(&mut self, _text: &str, _x: f64, _y: f64, _max_width: f64) {
-        // Would rasterize text using the font in state
-        // Uses self.state().font, text_align, text_baseline
+
+    fn fill_text_max(&mut self, text: &str, x: f64, y: f64, max_width: f64) {
+        if let Some(path) = self.text_path(text, x, y, max_width) {
+            self.fill_path(&path, FillRule::NonZero);
+        }
     }
-    
+
     fn stroke_text(&mut self, text: &str, x: f64, y: f64) {
         self.stroke_text_max(text, x, y, f64::INFINITY);
     }
-    
-    fn stroke_text_max(&mut self, _text: &str, _x: f64, _y: f64, _max_width: f64) {
-        // Would stroke text outline
+
+    fn stroke_text_max(&mut self, text: &str, x: f64, y: f64, max_width: f64) {
+        if let Some(path) = self.text_path(text, x, y, max_width) {
+            self.stroke_path(&path);
+        }
     }
-    
+
+    /// Metrics for the current font; all zero when no font is available
     fn measure_text(&self, text: &str) -> TextMetrics {
-        // Simple estimation: ~8 pixels per character
-        let width = text.len() as f64 * 8.0;
-        TextMetrics {
-            width,
-            actual_bounding_box_left: 0.0,
-            actual_bounding_box_right: width,
-            font_bounding_box_ascent: 10.0,
-            font_bounding_box_descent: 3.0,
-            actual_bounding_box_ascent: 10.0,
-            actual_bounding_box_descent: 3.0,
-            em_height_ascent: 10.0,
-            em_height_descent: 3.0,
-            hanging_baseline: 8.0,
-            alphabetic_baseline: 0.0,
-            ideographic_baseline: -3.0,
-        }
+        self.layout_text(text).map(|l| l.metrics).unwrap_or_default()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::CanvasRenderingContext2D;
-    
+
+    /// DejaVu Sans, when installed
+    fn test_fonts() -> Option<Arc<CanvasFonts>> {
+        let mut db = FontDatabase::new();
+        db.load_font_file("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf".as_ref()).ok()?;
+        Some(Arc::new(CanvasFonts::new(db)))
+    }
+
+    #[test]
+    fn test_font_shorthand() {
+        let font = CanvasFont::parse("italic small-caps 600 condensed 12pt/1.5 \"Open Sans\", Arial,  sans-serif").unwrap();
+        assert_eq!(font.style, FontStyle::Italic);
+        assert!(font.small_caps);
+        assert_eq!(font.weight, 600);
+        assert_eq!(font.size, 16.0);
+        assert_eq!(font.families, vec!["Open Sans", "Arial", "sans-serif"]);
+        assert_eq!(font.to_string(), "italic small-caps 600 condensed 16px \"Open Sans\", Arial, sans-serif");
+
+        assert_eq!(CanvasFont::parse("bold 2em serif").unwrap().to_string(), "bold 20px serif");
+        assert!(CanvasFont::parse("12px").is_none());
+        assert!(CanvasFont::parse("bold bold 12px serif").is_none());
+        assert!(CanvasFont::parse("12 serif").is_none());
+
+        let mut ctx = CanvasRenderingContext2D::new(10, 10);
+        ctx.set_font("bogus");
+        assert_eq!(ctx.state().font, "10px sans-serif");
+    }
+
     #[test]
     fn test_measure_text() {
-        let ctx = CanvasRenderingContext2D::new(100, 100);
+        let Some(fonts) = test_fonts() else { return };
+        let mut ctx = CanvasRenderingContext2D::new(100, 100);
+        ctx.set_fonts(fonts);
+        ctx.set_font("20px sans-serif");
         let metrics = ctx.measure_text("Hello");
-        assert!(metrics.width > 0.0);
+        assert!(metrics.width > 40.0 && metrics.width < 70.0);
+        // Ink starts near the pen position and 'H' reaches cap height
+        assert!(metrics.actual_bounding_box_left.abs() < 3.0);
+        assert!((metrics.actual_bounding_box_right - metrics.width).abs() < 3.0);
+        assert!(metrics.actual_bounding_box_ascent > 13.0 && metrics.actual_bounding_box_ascent < 16.0);
+        assert!(metrics.actual_bounding_box_descent.abs() < 1.0);
+        assert!(metrics.font_bounding_box_ascent > metrics.actual_bounding_box_ascent);
+        assert!((metrics.em_height_ascent + metrics.em_height_descent - 20.0).abs() < 1e-9);
+        assert_eq!(metrics.alphabetic_baseline, 0.0);
+
+        // Metrics are relative to the alignment point and baseline
+        ctx.state_mut().text_align = TextAlign::Center;
+        ctx.state_mut().text_baseline = TextBaseline::Top;
+        let centered = ctx.measure_text("Hello");
+        assert!((centered.actual_bounding_box_left - metrics.width / 2.0).abs() < 3.0);
+        assert!((centered.em_height_ascent).abs() < 1e-9);
+        assert!((centered.alphabetic_baseline + metrics.em_height_ascent).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_restricted_families() {
+        let mut db = FontDatabase::new();
+        for file in ["DejaVuSans.ttf", "DejaVuSansMono.ttf"] {
+            if db.load_font_file(format!("/usr/share/fonts/truetype/dejavu/{}", file).as_ref()).is_err() {
+                return;
+            }
+        }
+        let fonts = Arc::new(CanvasFonts::new(db));
+        let width = |fonts: &Arc<CanvasFonts>, font: &str| {
+            let mut ctx = CanvasRenderingContext2D::new(10, 10);
+            ctx.set_fonts(fonts.clone());
+            ctx.set_font(font);
+            ctx.measure_text("mmmmmlli").width
+        };
+        let fallback = width(&fonts, "20px Unknown");
+        assert_ne!(width(&fonts, "20px 'DejaVu Sans Mono'"), fallback);
+
+        // A family left out matches like one that isn't installed
+        let restricted = Arc::new(fonts.with_families(vec!["DejaVu Sans".into()]));
+        assert_eq!(width(&restricted, "20px 'DejaVu Sans Mono'"), fallback);
+        assert_eq!(width(&restricted, "20px 'dejavu sans'"), width(&fonts, "20px 'DejaVu Sans'"));
+    }
+
+    #[test]
+    fn test_fill_text() {
+        let Some(fonts) = test_fonts() else { return };
+        let mut ctx = CanvasRenderingContext2D::new(60, 30);
+        ctx.set_fonts(fonts);
+        ctx.set_font("bold 20px sans-serif");
+        ctx.state_mut().text_baseline = TextBaseline::Top;
+        ctx.fill_text("I", 10.0, 5.0);
+        let metrics = ctx.measure_text("I");
+        let ink = |ctx: &CanvasRenderingContext2D| {
+            (0..60u32).filter(|&x| (0..30u32).any(|y| ctx.data()[((y * 60 + x) * 4 + 3) as usize] > 128)).collect::<Vec<_>>()
+        };
+        // The stem is drawn right of x, where the ink box says it is
+        let columns = ink(&ctx);
+        assert!(!columns.is_empty());
+        assert!((columns[0] as f64 - (10.0 - metrics.actual_bounding_box_left)).abs() <= 1.0);
+
+        // maxWidth condenses the text horizontally
+        let mut narrow = CanvasRenderingContext2D::new(60, 30);
+        narrow.set_fonts(ctx.fonts());
+        narrow.set_font("bold 20px sans-serif");
+        narrow.fill_text_max("IIII", 0.0, 20.0, 10.0);
+        assert!(ink(&narrow).iter().all(|&x| x <= 10));
     }
 }
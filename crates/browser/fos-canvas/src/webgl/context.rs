@@ -1,9 +1,23 @@
 //! WebGL Rendering Context
 //!
-//! WebGL 1.0 implementation.
+//! WebGL 1.0 and 2.0 on the fos-render GL device. Shaders are translated
+//! from GLSL ES, and draw calls execute against the drawing buffer, which the
+//! canvas composites into its layer through `take_frame`.
 
 use std::collections::HashMap;
 
+use fos_render::gpu::gl::{
+    AttribPointer, BlendEquation, BlendFactor, BlendState, CompareFunc, ComponentType, CullFace, Filter,
+    PixelFormat, Primitive, TexParam, Wrap,
+};
+use fos_render::gpu::glsl::{self, ShaderModule, Stage};
+use fos_render::gpu::GlDevice;
+
+use super::framebuffer::{
+    RenderbufferFormat, WebGLFramebuffer, WebGLRenderbuffer, GL_DEPTH_ATTACHMENT, GL_DEPTH_STENCIL_ATTACHMENT,
+    GL_FRAMEBUFFER_COMPLETE,
+};
+
 /// WebGL rendering context
 #[derive(Debug)]
 pub struct WebGLRenderingContext {
@@ -11,6 +25,10 @@ pub struct WebGLRenderingContext {
     width: u32,
     /// Canvas height
     height: u32,
+    /// 1 or 2
+    version: u32,
+    /// Device executing draw calls
+    device: GlDevice,
     /// Current program
     current_program: Option<WebGLProgram>,
     /// Active texture unit
@@ -23,6 +41,26 @@ pub struct WebGLRenderingContext {
     clear_depth: f32,
     /// Enabled capabilities
     enabled: HashMap<u32, bool>,
+    /// Blend, depth and scissor settings applied when enabled
+    blend: BlendState,
+    depth_func: CompareFunc,
+    cull_face: CullFace,
+    scissor: (i32, i32, i32, i32),
+    /// Bound ARRAY_BUFFER
+    array_buffer: Option<u32>,
+    /// Translated shaders by shader id
+    shader_modules: HashMap<u32, ShaderModule>,
+    /// Device programs by program id
+    linked_programs: HashMap<u32, u32>,
+    /// bindAttribLocation calls by program id
+    attrib_bindings: HashMap<u32, Vec<(String, u32)>>,
+    /// Compile and link logs by shader or program id
+    info_logs: HashMap<u32, String>,
+    next_id: u32,
+    /// First error since the last `get_error`
+    error: u32,
+    /// Keep the drawing buffer after compositing
+    preserve_drawing_buffer: bool,
 }
 
 /// WebGL program
@@ -94,6 +132,18 @@ pub enum TextureFormat {
     LuminanceAlpha,
 }
 
+impl TextureFormat {
+    fn pixel_format(&self) -> PixelFormat {
+        match self {
+            Self::Rgba => PixelFormat::Rgba,
+            Self::Rgb => PixelFormat::Rgb,
+            Self::Alpha => PixelFormat::Alpha,
+            Self::Luminance => PixelFormat::Luminance,
+            Self::LuminanceAlpha => PixelFormat::LuminanceAlpha,
+        }
+    }
+}
+
 /// Uniform location
 #[derive(Debug, Clone)]
 pub struct WebGLUniformLocation {
@@ -123,9 +173,40 @@ pub const GL_RGB: u32 = 0x1907;
 pub const GL_DEPTH_TEST: u32 = 0x0B71;
 pub const GL_BLEND: u32 = 0x0BE2;
 pub const GL_CULL_FACE: u32 = 0x0B44;
+pub const GL_SCISSOR_TEST: u32 = 0x0C11;
 pub const GL_TRIANGLES: u32 = 0x0004;
+pub const GL_TRIANGLE_STRIP: u32 = 0x0005;
+pub const GL_TRIANGLE_FAN: u32 = 0x0006;
 pub const GL_LINES: u32 = 0x0001;
 pub const GL_POINTS: u32 = 0x0000;
+pub const GL_FLOAT: u32 = 0x1406;
+pub const GL_UNSIGNED_BYTE: u32 = 0x1401;
+pub const GL_UNSIGNED_SHORT: u32 = 0x1403;
+pub const GL_COLOR_BUFFER_BIT: u32 = 0x4000;
+pub const GL_DEPTH_BUFFER_BIT: u32 = 0x0100;
+pub const GL_STENCIL_BUFFER_BIT: u32 = 0x0400;
+pub const GL_COMPILE_STATUS: u32 = 0x8B81;
+pub const GL_LINK_STATUS: u32 = 0x8B82;
+pub const GL_TEXTURE0: u32 = 0x84C0;
+pub const GL_TEXTURE_MAG_FILTER: u32 = 0x2800;
+pub const GL_TEXTURE_MIN_FILTER: u32 = 0x2801;
+pub const GL_TEXTURE_WRAP_S: u32 = 0x2802;
+pub const GL_TEXTURE_WRAP_T: u32 = 0x2803;
+pub const GL_NEAREST: u32 = 0x2600;
+pub const GL_LINEAR: u32 = 0x2601;
+pub const GL_CLAMP_TO_EDGE: u32 = 0x812F;
+pub const GL_SRC_ALPHA: u32 = 0x0302;
+pub const GL_ONE_MINUS_SRC_ALPHA: u32 = 0x0303;
+pub const GL_FUNC_ADD: u32 = 0x8006;
+pub const GL_LESS: u32 = 0x0201;
+pub const GL_BACK: u32 = 0x0405;
+pub const GL_CW: u32 = 0x0900;
+pub const GL_CCW: u32 = 0x0901;
+pub const GL_NO_ERROR: u32 = 0;
+pub const GL_INVALID_ENUM: u32 = 0x0500;
+pub const GL_INVALID_VALUE: u32 = 0x0501;
+pub const GL_INVALID_OPERATION: u32 = 0x0502;
+pub const GL_INVALID_FRAMEBUFFER_OPERATION: u32 = 0x0506;
 
 impl WebGLRenderingContext {
     /// Create new WebGL context
@@ -133,41 +214,111 @@ impl WebGLRenderingContext {
         Self {
             width,
             height,
+            version: 1,
+            device: GlDevice::new(width, height),
             current_program: None,
             active_texture: 0,
             viewport: (0, 0, width as i32, height as i32),
             clear_color: (0.0, 0.0, 0.0, 0.0),
             clear_depth: 1.0,
             enabled: HashMap::new(),
+            blend: BlendState::default(),
+            depth_func: CompareFunc::Less,
+            cull_face: CullFace::Back,
+            scissor: (0, 0, width as i32, height as i32),
+            array_buffer: None,
+            shader_modules: HashMap::new(),
+            linked_programs: HashMap::new(),
+            attrib_bindings: HashMap::new(),
+            info_logs: HashMap::new(),
+            next_id: 1,
+            error: GL_NO_ERROR,
+            preserve_drawing_buffer: false,
+        }
+    }
+
+    /// Create new WebGL 2 context
+    pub fn new_webgl2(width: u32, height: u32) -> Self {
+        Self { version: 2, ..Self::new(width, height) }
+    }
+
+    pub fn is_webgl2(&self) -> bool {
+        self.version >= 2
+    }
+
+    pub fn drawing_buffer_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn drawing_buffer_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Resize the drawing buffer, clearing it
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.device.resize(width, height);
+    }
+
+    /// Keep drawing buffer contents after compositing
+    pub fn set_preserve_drawing_buffer(&mut self, preserve: bool) {
+        self.preserve_drawing_buffer = preserve;
+    }
+
+    /// Return and reset the first recorded error
+    pub fn get_error(&mut self) -> u32 {
+        std::mem::replace(&mut self.error, GL_NO_ERROR)
+    }
+
+    fn record_error(&mut self, error: u32) {
+        if self.error == GL_NO_ERROR {
+            self.error = error;
         }
     }
-    
+
+    fn alloc_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
     // Viewport and clear
-    
+
     pub fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
         self.viewport = (x, y, width, height);
+        self.device.viewport = self.viewport;
     }
-    
+
+    pub fn scissor(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.scissor = (x, y, width, height);
+        self.sync_capabilities();
+    }
+
     pub fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
         self.clear_color = (r, g, b, a);
+        self.device.clear_color = [r, g, b, a];
     }
-    
+
     pub fn clear_depth(&mut self, depth: f32) {
         self.clear_depth = depth;
+        self.device.clear_depth = depth;
     }
-    
-    pub fn clear(&mut self, _mask: u32) {
-        // Would clear buffers
+
+    pub fn clear(&mut self, mask: u32) {
+        if mask & !(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT | GL_STENCIL_BUFFER_BIT) != 0 {
+            return self.record_error(GL_INVALID_VALUE);
+        }
+        if self.device.clear(mask & GL_COLOR_BUFFER_BIT != 0, mask & GL_DEPTH_BUFFER_BIT != 0).is_err() {
+            self.record_error(GL_INVALID_FRAMEBUFFER_OPERATION);
+        }
     }
-    
+
     // Shaders
-    
-    pub fn create_shader(&self, shader_type: u32) -> WebGLShader {
-        static mut SHADER_ID: u32 = 0;
-        let id = unsafe { SHADER_ID += 1; SHADER_ID };
-        
+
+    pub fn create_shader(&mut self, shader_type: u32) -> WebGLShader {
         WebGLShader {
-            id,
+            id: self.alloc_id(),
             shader_type: if shader_type == GL_VERTEX_SHADER {
                 ShaderType::Vertex
             } else {
@@ -177,189 +328,706 @@ impl WebGLRenderingContext {
             compiled: false,
         }
     }
-    
+
     pub fn shader_source(&self, shader: &mut WebGLShader, source: &str) {
         shader.source = source.to_string();
     }
-    
-    pub fn compile_shader(&self, shader: &mut WebGLShader) {
-        // Would compile shader
-        shader.compiled = true;
+
+    /// Translate the shader's GLSL ES source; failures go to the info log
+    pub fn compile_shader(&mut self, shader: &mut WebGLShader) {
+        let stage = match shader.shader_type {
+            ShaderType::Vertex => Stage::Vertex,
+            ShaderType::Fragment => Stage::Fragment,
+        };
+        let result = glsl::translate(&shader.source, stage).and_then(|module| {
+            if module.version >= 300 && !self.is_webgl2() {
+                Err(fos_render::GpuError::ShaderError("GLSL ES 3.00 requires WebGL 2".into()))
+            } else {
+                Ok(module)
+            }
+        });
+        match result {
+            Ok(module) => {
+                self.shader_modules.insert(shader.id, module);
+                self.info_logs.remove(&shader.id);
+                shader.compiled = true;
+            }
+            Err(e) => {
+                self.shader_modules.remove(&shader.id);
+                self.info_logs.insert(shader.id, format!("ERROR: {}", e));
+                shader.compiled = false;
+            }
+        }
     }
-    
+
     pub fn get_shader_parameter(&self, shader: &WebGLShader, _pname: u32) -> bool {
         shader.compiled
     }
-    
+
+    pub fn get_shader_info_log(&self, shader: &WebGLShader) -> String {
+        self.info_logs.get(&shader.id).cloned().unwrap_or_default()
+    }
+
     // Programs
-    
-    pub fn create_program(&self) -> WebGLProgram {
-        static mut PROGRAM_ID: u32 = 0;
-        let id = unsafe { PROGRAM_ID += 1; PROGRAM_ID };
-        
+
+    pub fn create_program(&mut self) -> WebGLProgram {
         WebGLProgram {
-            id,
+            id: self.alloc_id(),
             vertex_shader: None,
             fragment_shader: None,
             linked: false,
         }
     }
-    
+
     pub fn attach_shader(&self, program: &mut WebGLProgram, shader: &WebGLShader) {
         match shader.shader_type {
             ShaderType::Vertex => program.vertex_shader = Some(shader.clone()),
             ShaderType::Fragment => program.fragment_shader = Some(shader.clone()),
         }
     }
-    
-    pub fn link_program(&self, program: &mut WebGLProgram) {
-        program.linked = program.vertex_shader.is_some() && program.fragment_shader.is_some();
+
+    /// Take effect at the next `link_program`
+    pub fn bind_attrib_location(&mut self, program: &WebGLProgram, index: u32, name: &str) {
+        let bindings = self.attrib_bindings.entry(program.id).or_default();
+        bindings.retain(|(n, _)| n != name);
+        bindings.push((name.to_string(), index));
     }
-    
+
+    pub fn link_program(&mut self, program: &mut WebGLProgram) {
+        let modules = program
+            .vertex_shader
+            .as_ref()
+            .zip(program.fragment_shader.as_ref())
+            .and_then(|(vs, fs)| self.shader_modules.get(&vs.id).zip(self.shader_modules.get(&fs.id)));
+        let result = match modules {
+            Some((vs, fs)) => {
+                let bindings = self.attrib_bindings.get(&program.id).cloned().unwrap_or_default();
+                self.device.link_program(vs, fs, &bindings).map_err(|e| e.to_string())
+            }
+            None => Err("program needs compiled vertex and fragment shaders".to_string()),
+        };
+        if let Some(old) = self.linked_programs.remove(&program.id) {
+            self.device.delete_program(old);
+        }
+        match result {
+            Ok(linked) => {
+                self.linked_programs.insert(program.id, linked);
+                self.info_logs.remove(&program.id);
+                program.linked = true;
+            }
+            Err(log) => {
+                self.info_logs.insert(program.id, log);
+                program.linked = false;
+            }
+        }
+        // Relinking the current program updates what draws use
+        if self.current_program.as_ref().is_some_and(|p| p.id == program.id) {
+            self.use_program(Some(program.clone()));
+        }
+    }
+
+    pub fn get_program_parameter(&self, program: &WebGLProgram, _pname: u32) -> bool {
+        program.linked
+    }
+
+    pub fn get_program_info_log(&self, program: &WebGLProgram) -> String {
+        self.info_logs.get(&program.id).cloned().unwrap_or_default()
+    }
+
     pub fn use_program(&mut self, program: Option<WebGLProgram>) {
+        let linked = program.as_ref().map(|p| self.linked_programs.get(&p.id).copied());
+        match linked {
+            Some(None) => return self.record_error(GL_INVALID_OPERATION),
+            Some(Some(id)) => self.device.use_program(Some(id)),
+            None => self.device.use_program(None),
+        }
         self.current_program = program;
     }
-    
-    pub fn get_attrib_location(&self, _program: &WebGLProgram, name: &str) -> GLint {
-        // Would look up attribute
-        name.len() as i32 % 16
+
+    pub fn get_attrib_location(&self, program: &WebGLProgram, name: &str) -> GLint {
+        self.linked_programs
+            .get(&program.id)
+            .and_then(|id| self.device.attrib_location(*id, name))
+            .map_or(-1, |location| location as i32)
     }
-    
-    pub fn get_uniform_location(&self, program: &WebGLProgram, name: &str) -> WebGLUniformLocation {
-        WebGLUniformLocation {
+
+    /// `None` for names that aren't active uniforms
+    pub fn get_uniform_location(&self, program: &WebGLProgram, name: &str) -> Option<WebGLUniformLocation> {
+        let linked = *self.linked_programs.get(&program.id)?;
+        self.device.uniform_type(linked, name)?;
+        let base = name.split('[').next().unwrap_or(name);
+        let location = self.device.active_uniforms(linked).iter().position(|u| u.name.split('[').next() == Some(base));
+        Some(WebGLUniformLocation {
             program_id: program.id,
-            location: name.len() as i32 % 16,
+            location: location.map_or(-1, |l| l as i32),
             name: name.to_string(),
-        }
+        })
     }
-    
+
     // Buffers
-    
-    pub fn create_buffer(&self) -> WebGLBuffer {
-        static mut BUFFER_ID: u32 = 0;
-        let id = unsafe { BUFFER_ID += 1; BUFFER_ID };
-        
+
+    pub fn create_buffer(&mut self) -> WebGLBuffer {
         WebGLBuffer {
-            id,
+            id: self.device.create_buffer(),
             target: BufferTarget::ArrayBuffer,
             data: Vec::new(),
             usage: BufferUsage::StaticDraw,
         }
     }
-    
-    pub fn bind_buffer(&self, _target: u32, _buffer: &WebGLBuffer) {
-        // Would bind buffer
+
+    pub fn delete_buffer(&mut self, buffer: &WebGLBuffer) {
+        self.device.delete_buffer(buffer.id);
+        if self.array_buffer == Some(buffer.id) {
+            self.array_buffer = None;
+        }
     }
-    
-    pub fn buffer_data(&self, buffer: &mut WebGLBuffer, data: &[u8], usage: u32) {
+
+    pub fn bind_buffer(&mut self, target: u32, buffer: &WebGLBuffer) {
+        match target {
+            GL_ARRAY_BUFFER => self.array_buffer = Some(buffer.id),
+            GL_ELEMENT_ARRAY_BUFFER => self.device.bind_element_buffer(Some(buffer.id)),
+            _ => self.record_error(GL_INVALID_ENUM),
+        }
+    }
+
+    pub fn buffer_data(&mut self, buffer: &mut WebGLBuffer, data: &[u8], usage: u32) {
         buffer.data = data.to_vec();
         buffer.usage = match usage {
             GL_STATIC_DRAW => BufferUsage::StaticDraw,
             GL_DYNAMIC_DRAW => BufferUsage::DynamicDraw,
             _ => BufferUsage::StreamDraw,
         };
+        if self.device.buffer_data(buffer.id, data).is_err() {
+            self.record_error(GL_INVALID_OPERATION);
+        }
+    }
+
+    pub fn buffer_sub_data(&mut self, buffer: &mut WebGLBuffer, offset: usize, data: &[u8]) {
+        if self.device.buffer_sub_data(buffer.id, offset, data).is_err() {
+            return self.record_error(GL_INVALID_VALUE);
+        }
+        buffer.data[offset..offset + data.len()].copy_from_slice(data);
     }
-    
+
     // Textures
-    
-    pub fn create_texture(&self) -> WebGLTexture {
-        static mut TEXTURE_ID: u32 = 0;
-        let id = unsafe { TEXTURE_ID += 1; TEXTURE_ID };
-        
+
+    pub fn create_texture(&mut self) -> WebGLTexture {
         WebGLTexture {
-            id,
+            id: self.device.create_texture(),
             width: 0,
             height: 0,
             format: TextureFormat::Rgba,
             data: Vec::new(),
         }
     }
-    
-    pub fn bind_texture(&self, _target: u32, _texture: &WebGLTexture) {
-        // Would bind texture
+
+    pub fn delete_texture(&mut self, texture: &WebGLTexture) {
+        self.device.delete_texture(texture.id);
+    }
+
+    /// Bind to the active texture unit
+    pub fn bind_texture(&mut self, _target: u32, texture: &WebGLTexture) {
+        self.device.bind_texture(self.active_texture as usize, Some(texture.id));
     }
-    
-    pub fn tex_image_2d(&self, texture: &mut WebGLTexture, width: u32, height: u32, data: &[u8]) {
+
+    /// Upload pixels in the texture's format, rows starting at t = 0; empty data allocates only
+    pub fn tex_image_2d(&mut self, texture: &mut WebGLTexture, width: u32, height: u32, data: &[u8]) {
+        let upload = (!data.is_empty()).then_some(data);
+        if self.device.tex_image_2d(texture.id, width, height, texture.format.pixel_format(), upload).is_err() {
+            return self.record_error(GL_INVALID_VALUE);
+        }
         texture.width = width;
         texture.height = height;
         texture.data = data.to_vec();
     }
-    
+
+    pub fn tex_sub_image_2d(&mut self, texture: &WebGLTexture, x: u32, y: u32, width: u32, height: u32, data: &[u8]) {
+        let format = texture.format.pixel_format();
+        if self.device.tex_sub_image_2d(texture.id, x, y, width, height, format, data).is_err() {
+            self.record_error(GL_INVALID_VALUE);
+        }
+    }
+
+    pub fn tex_parameteri(&mut self, texture: &WebGLTexture, pname: u32, param: i32) {
+        let param = param as u32;
+        let update = match pname {
+            GL_TEXTURE_MIN_FILTER => Filter::from_gl(param).map(TexParam::MinFilter),
+            GL_TEXTURE_MAG_FILTER => Filter::from_gl(param).filter(|f| matches!(f, Filter::Nearest | Filter::Linear)).map(TexParam::MagFilter),
+            GL_TEXTURE_WRAP_S => Wrap::from_gl(param).map(TexParam::WrapS),
+            GL_TEXTURE_WRAP_T => Wrap::from_gl(param).map(TexParam::WrapT),
+            _ => None,
+        };
+        match update {
+            Some(update) if self.device.tex_parameter(texture.id, update).is_ok() => {}
+            Some(_) => self.record_error(GL_INVALID_OPERATION),
+            None => self.record_error(GL_INVALID_ENUM),
+        }
+    }
+
+    pub fn generate_mipmap(&mut self, texture: &WebGLTexture) {
+        if self.device.generate_mipmap(texture.id).is_err() {
+            self.record_error(GL_INVALID_OPERATION);
+        }
+    }
+
+    /// Accepts a unit index or `GL_TEXTURE0 + index`
     pub fn active_texture(&mut self, unit: u32) {
-        self.active_texture = unit;
+        self.active_texture = unit.checked_sub(GL_TEXTURE0).unwrap_or(unit);
+    }
+
+    // Framebuffers
+
+    pub fn create_framebuffer(&mut self) -> WebGLFramebuffer {
+        WebGLFramebuffer::new(self.device.create_framebuffer())
+    }
+
+    pub fn delete_framebuffer(&mut self, framebuffer: &WebGLFramebuffer) {
+        self.device.delete_framebuffer(framebuffer.id);
     }
-    
+
+    /// Bind a framebuffer, or `None` for the drawing buffer
+    pub fn bind_framebuffer(&mut self, _target: u32, framebuffer: Option<&WebGLFramebuffer>) {
+        self.device.bind_framebuffer(framebuffer.map(|fb| fb.id));
+    }
+
+    pub fn framebuffer_texture_2d(&mut self, framebuffer: &mut WebGLFramebuffer, texture: Option<&WebGLTexture>) {
+        if self.device.framebuffer_texture(framebuffer.id, texture.map(|t| t.id)).is_err() {
+            return self.record_error(GL_INVALID_OPERATION);
+        }
+        framebuffer.color_attachment = texture.map(|t| t.id);
+        if let Some(texture) = texture {
+            framebuffer.width = texture.width;
+            framebuffer.height = texture.height;
+        }
+    }
+
+    pub fn create_renderbuffer(&mut self) -> WebGLRenderbuffer {
+        WebGLRenderbuffer::new(self.device.create_renderbuffer())
+    }
+
+    /// Only depth formats are backed by storage; color renderbuffers read as incomplete
+    pub fn renderbuffer_storage(&mut self, renderbuffer: &mut WebGLRenderbuffer, format: RenderbufferFormat, width: u32, height: u32) {
+        renderbuffer.storage(format, width, height);
+        if self.device.renderbuffer_storage(renderbuffer.id, width, height).is_err() {
+            self.record_error(GL_INVALID_OPERATION);
+        }
+    }
+
+    pub fn framebuffer_renderbuffer(
+        &mut self,
+        framebuffer: &mut WebGLFramebuffer,
+        attachment: u32,
+        renderbuffer: Option<&WebGLRenderbuffer>,
+    ) {
+        if !matches!(attachment, GL_DEPTH_ATTACHMENT | GL_DEPTH_STENCIL_ATTACHMENT) {
+            return self.record_error(GL_INVALID_ENUM);
+        }
+        if self.device.framebuffer_depth(framebuffer.id, renderbuffer.map(|rb| rb.id)).is_err() {
+            return self.record_error(GL_INVALID_OPERATION);
+        }
+        framebuffer.depth_attachment = renderbuffer.map(|rb| rb.id);
+    }
+
+    pub fn check_framebuffer_status(&self, _target: u32) -> u32 {
+        // FRAMEBUFFER_INCOMPLETE_ATTACHMENT
+        if self.device.framebuffer_complete() { GL_FRAMEBUFFER_COMPLETE } else { 0x8CD6 }
+    }
+
     // Enable/Disable
-    
+
     pub fn enable(&mut self, cap: u32) {
         self.enabled.insert(cap, true);
+        self.sync_capabilities();
     }
-    
+
     pub fn disable(&mut self, cap: u32) {
         self.enabled.insert(cap, false);
+        self.sync_capabilities();
     }
-    
+
     pub fn is_enabled(&self, cap: u32) -> bool {
         *self.enabled.get(&cap).unwrap_or(&false)
     }
-    
+
+    fn sync_capabilities(&mut self) {
+        self.device.blend = self.is_enabled(GL_BLEND).then_some(self.blend);
+        self.device.depth_func = self.is_enabled(GL_DEPTH_TEST).then_some(self.depth_func);
+        self.device.cull_face = self.is_enabled(GL_CULL_FACE).then_some(self.cull_face);
+        self.device.scissor = self.is_enabled(GL_SCISSOR_TEST).then_some(self.scissor);
+    }
+
+    pub fn blend_func(&mut self, sfactor: u32, dfactor: u32) {
+        self.blend_func_separate(sfactor, dfactor, sfactor, dfactor);
+    }
+
+    pub fn blend_func_separate(&mut self, src_rgb: u32, dst_rgb: u32, src_alpha: u32, dst_alpha: u32) {
+        let factors = [src_rgb, dst_rgb, src_alpha, dst_alpha].map(BlendFactor::from_gl);
+        let [Some(src_rgb), Some(dst_rgb), Some(src_alpha), Some(dst_alpha)] = factors else {
+            return self.record_error(GL_INVALID_ENUM);
+        };
+        self.blend = BlendState { src_rgb, dst_rgb, src_alpha, dst_alpha, ..self.blend };
+        self.sync_capabilities();
+    }
+
+    pub fn blend_equation(&mut self, mode: u32) {
+        self.blend_equation_separate(mode, mode);
+    }
+
+    pub fn blend_equation_separate(&mut self, mode_rgb: u32, mode_alpha: u32) {
+        match (BlendEquation::from_gl(mode_rgb), BlendEquation::from_gl(mode_alpha)) {
+            (Some(equation_rgb), Some(equation_alpha)) => {
+                self.blend = BlendState { equation_rgb, equation_alpha, ..self.blend };
+                self.sync_capabilities();
+            }
+            _ => self.record_error(GL_INVALID_ENUM),
+        }
+    }
+
+    pub fn blend_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.blend.color = [r, g, b, a];
+        self.sync_capabilities();
+    }
+
+    pub fn depth_func(&mut self, func: u32) {
+        match CompareFunc::from_gl(func) {
+            Some(func) => {
+                self.depth_func = func;
+                self.sync_capabilities();
+            }
+            None => self.record_error(GL_INVALID_ENUM),
+        }
+    }
+
+    pub fn depth_mask(&mut self, flag: bool) {
+        self.device.depth_mask = flag;
+    }
+
+    pub fn color_mask(&mut self, r: bool, g: bool, b: bool, a: bool) {
+        self.device.color_mask = [r, g, b, a];
+    }
+
+    pub fn cull_face(&mut self, mode: u32) {
+        match CullFace::from_gl(mode) {
+            Some(mode) => {
+                self.cull_face = mode;
+                self.sync_capabilities();
+            }
+            None => self.record_error(GL_INVALID_ENUM),
+        }
+    }
+
+    pub fn front_face(&mut self, mode: u32) {
+        match mode {
+            GL_CW | GL_CCW => self.device.front_ccw = mode == GL_CCW,
+            _ => self.record_error(GL_INVALID_ENUM),
+        }
+    }
+
     // Drawing
-    
-    pub fn draw_arrays(&self, _mode: u32, _first: i32, _count: i32) {
-        // Would draw primitives
+
+    pub fn draw_arrays(&mut self, mode: u32, first: i32, count: i32) {
+        self.draw_arrays_instanced_unchecked(mode, first, count, 1);
     }
-    
-    pub fn draw_elements(&self, _mode: u32, _count: i32, _type: u32, _offset: i32) {
-        // Would draw indexed primitives
+
+    pub fn draw_elements(&mut self, mode: u32, count: i32, ty: u32, offset: i32) {
+        self.draw_elements_instanced_unchecked(mode, count, ty, offset, 1);
     }
-    
+
+    /// WebGL 2 instanced draw
+    pub fn draw_arrays_instanced(&mut self, mode: u32, first: i32, count: i32, instances: i32) {
+        if !self.is_webgl2() {
+            return self.record_error(GL_INVALID_OPERATION);
+        }
+        self.draw_arrays_instanced_unchecked(mode, first, count, instances);
+    }
+
+    /// WebGL 2 instanced indexed draw
+    pub fn draw_elements_instanced(&mut self, mode: u32, count: i32, ty: u32, offset: i32, instances: i32) {
+        if !self.is_webgl2() {
+            return self.record_error(GL_INVALID_OPERATION);
+        }
+        self.draw_elements_instanced_unchecked(mode, count, ty, offset, instances);
+    }
+
+    pub(crate) fn draw_arrays_instanced_unchecked(&mut self, mode: u32, first: i32, count: i32, instances: i32) {
+        let Some(mode) = Primitive::from_gl(mode) else {
+            return self.record_error(GL_INVALID_ENUM);
+        };
+        if first < 0 || count < 0 || instances < 0 {
+            return self.record_error(GL_INVALID_VALUE);
+        }
+        if self.device.draw_arrays(mode, first as u32, count as u32, instances as u32).is_err() {
+            self.record_error(GL_INVALID_OPERATION);
+        }
+    }
+
+    pub(crate) fn draw_elements_instanced_unchecked(&mut self, mode: u32, count: i32, ty: u32, offset: i32, instances: i32) {
+        let index_type = ComponentType::from_gl(ty)
+            .filter(|t| matches!(t, ComponentType::UnsignedByte | ComponentType::UnsignedShort | ComponentType::UnsignedInt));
+        let (Some(mode), Some(index_type)) = (Primitive::from_gl(mode), index_type) else {
+            return self.record_error(GL_INVALID_ENUM);
+        };
+        if count < 0 || offset < 0 || instances < 0 {
+            return self.record_error(GL_INVALID_VALUE);
+        }
+        let result = self.device.draw_elements(mode, count as usize, index_type, offset as usize, instances as u32);
+        if result.is_err() {
+            self.record_error(GL_INVALID_OPERATION);
+        }
+    }
+
+    /// RGBA8 pixels from the bound framebuffer, bottom row first
+    pub fn read_pixels(&self, x: i32, y: i32, width: u32, height: u32) -> Vec<u8> {
+        self.device.read_pixels(x, y, width, height)
+    }
+
+    /// Drawing buffer as top-down RGBA8 for the canvas layer; cleared
+    /// afterwards unless `preserveDrawingBuffer` is set
+    pub fn take_frame(&mut self) -> Vec<u8> {
+        let frame = self.device.frame();
+        if !self.preserve_drawing_buffer {
+            self.device.resize(self.width, self.height);
+        }
+        frame
+    }
+
     // Vertex attributes
-    
-    pub fn vertex_attrib_pointer(&self, _index: u32, _size: i32, _type: u32, _normalized: bool, _stride: i32, _offset: i32) {
-        // Would set vertex attribute pointer
+
+    /// Source the attribute from the bound ARRAY_BUFFER
+    pub fn vertex_attrib_pointer(&mut self, index: u32, size: i32, ty: u32, normalized: bool, stride: i32, offset: i32) {
+        let (Some(buffer), Some(component)) = (self.array_buffer, ComponentType::from_gl(ty)) else {
+            return self.record_error(if self.array_buffer.is_none() { GL_INVALID_OPERATION } else { GL_INVALID_ENUM });
+        };
+        if !(1..=4).contains(&size) || stride < 0 || offset < 0 {
+            return self.record_error(GL_INVALID_VALUE);
+        }
+        let pointer = AttribPointer {
+            buffer,
+            size: size as usize,
+            component,
+            normalized,
+            stride: stride as usize,
+            offset: offset as usize,
+        };
+        if self.device.vertex_attrib_pointer(index as usize, pointer).is_err() {
+            self.record_error(GL_INVALID_VALUE);
+        }
+    }
+
+    pub fn enable_vertex_attrib_array(&mut self, index: u32) {
+        self.device.enable_vertex_attrib(index as usize, true);
     }
-    
-    pub fn enable_vertex_attrib_array(&self, _index: u32) {
-        // Would enable vertex attrib
+
+    pub fn disable_vertex_attrib_array(&mut self, index: u32) {
+        self.device.enable_vertex_attrib(index as usize, false);
+    }
+
+    pub fn vertex_attrib4f(&mut self, index: u32, x: f32, y: f32, z: f32, w: f32) {
+        self.device.vertex_attrib(index as usize, [x, y, z, w]);
+    }
+
+    /// WebGL 2 per-instance attribute rate
+    pub fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32) {
+        if !self.is_webgl2() {
+            return self.record_error(GL_INVALID_OPERATION);
+        }
+        self.device.vertex_attrib_divisor(index as usize, divisor);
+    }
+
+    pub(crate) fn vertex_attrib_divisor_unchecked(&mut self, index: u32, divisor: u32) {
+        self.device.vertex_attrib_divisor(index as usize, divisor);
     }
-    
+
     // Uniforms
-    
-    pub fn uniform1f(&self, _location: &WebGLUniformLocation, _x: f32) {}
-    pub fn uniform2f(&self, _location: &WebGLUniformLocation, _x: f32, _y: f32) {}
-    pub fn uniform3f(&self, _location: &WebGLUniformLocation, _x: f32, _y: f32, _z: f32) {}
-    pub fn uniform4f(&self, _location: &WebGLUniformLocation, _x: f32, _y: f32, _z: f32, _w: f32) {}
-    pub fn uniform1i(&self, _location: &WebGLUniformLocation, _x: i32) {}
-    pub fn uniform_matrix4fv(&self, _location: &WebGLUniformLocation, _transpose: bool, _value: &[f32; 16]) {}
+
+    fn set_uniform(&mut self, location: &WebGLUniformLocation, data: &[f32]) {
+        let current = self.current_program.as_ref().filter(|p| p.id == location.program_id);
+        let Some(linked) = current.and_then(|p| self.linked_programs.get(&p.id).copied()) else {
+            return self.record_error(GL_INVALID_OPERATION);
+        };
+        if self.device.set_uniform(linked, &location.name, data).is_err() {
+            self.record_error(GL_INVALID_OPERATION);
+        }
+    }
+
+    pub fn uniform1f(&mut self, location: &WebGLUniformLocation, x: f32) {
+        self.set_uniform(location, &[x]);
+    }
+
+    pub fn uniform2f(&mut self, location: &WebGLUniformLocation, x: f32, y: f32) {
+        self.set_uniform(location, &[x, y]);
+    }
+
+    pub fn uniform3f(&mut self, location: &WebGLUniformLocation, x: f32, y: f32, z: f32) {
+        self.set_uniform(location, &[x, y, z]);
+    }
+
+    pub fn uniform4f(&mut self, location: &WebGLUniformLocation, x: f32, y: f32, z: f32, w: f32) {
+        self.set_uniform(location, &[x, y, z, w]);
+    }
+
+    pub fn uniform1i(&mut self, location: &WebGLUniformLocation, x: i32) {
+        self.set_uniform(location, &[x as f32]);
+    }
+
+    /// Any uniform, including arrays, from flattened floats
+    pub fn uniform_fv(&mut self, location: &WebGLUniformLocation, value: &[f32]) {
+        self.set_uniform(location, value);
+    }
+
+    pub fn uniform_matrix4fv(&mut self, location: &WebGLUniformLocation, transpose: bool, value: &[f32; 16]) {
+        // WebGL 1 requires transpose to be false
+        if transpose && !self.is_webgl2() {
+            return self.record_error(GL_INVALID_VALUE);
+        }
+        let mut matrix = *value;
+        if transpose {
+            matrix = std::array::from_fn(|i| value[(i % 4) * 4 + i / 4]);
+        }
+        self.set_uniform(location, &matrix);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
     #[test]
     fn test_create_context() {
         let ctx = WebGLRenderingContext::new(800, 600);
         assert_eq!(ctx.viewport, (0, 0, 800, 600));
     }
-    
+
     #[test]
     fn test_create_program() {
-        let ctx = WebGLRenderingContext::new(800, 600);
+        let mut ctx = WebGLRenderingContext::new(800, 600);
         let mut program = ctx.create_program();
-        
+
         let mut vs = ctx.create_shader(GL_VERTEX_SHADER);
         ctx.shader_source(&mut vs, "void main() {}");
         ctx.compile_shader(&mut vs);
-        
+
         let mut fs = ctx.create_shader(GL_FRAGMENT_SHADER);
         ctx.shader_source(&mut fs, "void main() {}");
         ctx.compile_shader(&mut fs);
-        
+
         ctx.attach_shader(&mut program, &vs);
         ctx.attach_shader(&mut program, &fs);
         ctx.link_program(&mut program);
-        
+
         assert!(program.linked);
+
+        let mut bad = ctx.create_shader(GL_FRAGMENT_SHADER);
+        ctx.shader_source(&mut bad, "#version 300 es\nvoid main() {}");
+        ctx.compile_shader(&mut bad);
+        assert!(!bad.compiled);
+        assert!(ctx.get_shader_info_log(&bad).contains("WebGL 2"));
+    }
+
+    fn compile(ctx: &mut WebGLRenderingContext, vertex: &str, fragment: &str) -> WebGLProgram {
+        let mut program = ctx.create_program();
+        for (ty, source) in [(GL_VERTEX_SHADER, vertex), (GL_FRAGMENT_SHADER, fragment)] {
+            let mut shader = ctx.create_shader(ty);
+            ctx.shader_source(&mut shader, source);
+            ctx.compile_shader(&mut shader);
+            assert!(shader.compiled, "{}", ctx.get_shader_info_log(&shader));
+            ctx.attach_shader(&mut program, &shader);
+        }
+        ctx.link_program(&mut program);
+        assert!(program.linked, "{}", ctx.get_program_info_log(&program));
+        program
+    }
+
+    fn floats(data: &[f32]) -> Vec<u8> {
+        data.iter().flat_map(|f| f.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_draw_textured_quad() {
+        let mut ctx = WebGLRenderingContext::new(4, 4);
+        let program = compile(
+            &mut ctx,
+            "attribute vec2 a_pos; varying vec2 v_uv;
+             void main() { v_uv = a_pos * 0.5 + 0.5; gl_Position = vec4(a_pos, 0.0, 1.0); }",
+            "precision mediump float; uniform sampler2D u_tex; uniform vec4 u_tint; varying vec2 v_uv;
+             void main() { gl_FragColor = texture2D(u_tex, v_uv) * u_tint; }",
+        );
+        ctx.use_program(Some(program.clone()));
+
+        // 2x1 texture: red on the left, green on the right
+        let mut texture = ctx.create_texture();
+        ctx.bind_texture(GL_TEXTURE_2D, &texture);
+        ctx.tex_image_2d(&mut texture, 2, 1, &[255, 0, 0, 255, 0, 255, 0, 255]);
+        ctx.tex_parameteri(&texture, GL_TEXTURE_MIN_FILTER, GL_NEAREST as i32);
+        ctx.tex_parameteri(&texture, GL_TEXTURE_MAG_FILTER, GL_NEAREST as i32);
+        let sampler = ctx.get_uniform_location(&program, "u_tex").unwrap();
+        ctx.uniform1i(&sampler, 0);
+        let tint = ctx.get_uniform_location(&program, "u_tint").unwrap();
+        ctx.uniform4f(&tint, 1.0, 1.0, 1.0, 1.0);
+        assert!(ctx.get_uniform_location(&program, "u_missing").is_none());
+
+        let mut buffer = ctx.create_buffer();
+        ctx.bind_buffer(GL_ARRAY_BUFFER, &buffer);
+        ctx.buffer_data(&mut buffer, &floats(&[-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0]), GL_STATIC_DRAW);
+        let position = ctx.get_attrib_location(&program, "a_pos") as u32;
+        ctx.vertex_attrib_pointer(position, 2, GL_FLOAT, false, 0, 0);
+        ctx.enable_vertex_attrib_array(position);
+        ctx.draw_arrays(GL_TRIANGLE_STRIP, 0, 4);
+        assert_eq!(ctx.get_error(), GL_NO_ERROR);
+
+        let pixels = ctx.read_pixels(0, 0, 4, 1);
+        assert_eq!(pixels[0..4], [255, 0, 0, 255]);
+        assert_eq!(pixels[12..16], [0, 255, 0, 255]);
+
+        // Compositing hands over the frame and clears the drawing buffer
+        let frame = ctx.take_frame();
+        assert_eq!(frame.len(), 4 * 4 * 4);
+        assert_eq!(frame[12 * 4..12 * 4 + 4], [255, 0, 0, 255]);
+        assert_eq!(ctx.read_pixels(0, 0, 1, 1), [0, 0, 0, 0]);
+
+        // Drawing without a buffer-backed attribute in range is an error
+        ctx.draw_arrays(GL_TRIANGLES, 0, 6);
+        assert_eq!(ctx.get_error(), GL_INVALID_OPERATION);
+    }
+
+    #[test]
+    fn test_webgl2_instancing() {
+        let mut gl1 = WebGLRenderingContext::new(4, 4);
+        gl1.vertex_attrib_divisor(0, 1);
+        assert_eq!(gl1.get_error(), GL_INVALID_OPERATION);
+
+        let mut ctx = WebGLRenderingContext::new_webgl2(4, 1);
+        let program = compile(
+            &mut ctx,
+            "#version 300 es
+             in vec2 a_pos; in float a_offset;
+             void main() { gl_Position = vec4(a_pos.x * 0.25 + a_offset, a_pos.y, 0.0, 1.0); }",
+            "#version 300 es
+             precision mediump float; out vec4 color;
+             void main() { color = vec4(0.0, 0.0, 1.0, 1.0); }",
+        );
+        ctx.use_program(Some(program.clone()));
+
+        // One quad per instance, a quarter of the width wide
+        let mut quad = ctx.create_buffer();
+        ctx.bind_buffer(GL_ARRAY_BUFFER, &quad);
+        ctx.buffer_data(&mut quad, &floats(&[-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0]), GL_STATIC_DRAW);
+        let pos = ctx.get_attrib_location(&program, "a_pos") as u32;
+        ctx.vertex_attrib_pointer(pos, 2, GL_FLOAT, false, 0, 0);
+        ctx.enable_vertex_attrib_array(pos);
+
+        let mut offsets = ctx.create_buffer();
+        ctx.bind_buffer(GL_ARRAY_BUFFER, &offsets);
+        ctx.buffer_data(&mut offsets, &floats(&[-0.75, 0.75]), GL_STATIC_DRAW);
+        let offset = ctx.get_attrib_location(&program, "a_offset") as u32;
+        ctx.vertex_attrib_pointer(offset, 1, GL_FLOAT, false, 0, 0);
+        ctx.enable_vertex_attrib_array(offset);
+        ctx.vertex_attrib_divisor(offset, 1);
+
+        ctx.draw_arrays_instanced(GL_TRIANGLE_STRIP, 0, 4, 2);
+        assert_eq!(ctx.get_error(), GL_NO_ERROR);
+        let row = ctx.read_pixels(0, 0, 4, 1);
+        let blue: Vec<bool> = row.chunks(4).map(|p| p == [0, 0, 255, 255]).collect();
+        assert_eq!(blue, vec![true, false, false, true]);
     }
 }
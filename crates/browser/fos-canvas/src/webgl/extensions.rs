@@ -4,6 +4,8 @@
 
 use std::collections::HashMap;
 
+use super::context::WebGLRenderingContext;
+
 /// Extension registry
 #[derive(Debug, Default)]
 pub struct ExtensionRegistry {
@@ -109,16 +111,24 @@ impl Default for OesVertexArrayObject {
     }
 }
 
-/// ANGLE_instanced_arrays extension
+/// ANGLE_instanced_arrays extension; WebGL 2 has these built in
 #[derive(Debug, Default)]
 pub struct AngleInstancedArrays;
 
 impl AngleInstancedArrays {
     pub fn new() -> Self { Self }
     
-    pub fn draw_arrays_instanced(&self, _mode: u32, _first: i32, _count: i32, _primcount: i32) {}
-    pub fn draw_elements_instanced(&self, _mode: u32, _count: i32, _type: u32, _offset: i32, _primcount: i32) {}
-    pub fn vertex_attrib_divisor(&self, _index: u32, _divisor: u32) {}
+    pub fn draw_arrays_instanced(&self, gl: &mut WebGLRenderingContext, mode: u32, first: i32, count: i32, primcount: i32) {
+        gl.draw_arrays_instanced_unchecked(mode, first, count, primcount);
+    }
+
+    pub fn draw_elements_instanced(&self, gl: &mut WebGLRenderingContext, mode: u32, count: i32, ty: u32, offset: i32, primcount: i32) {
+        gl.draw_elements_instanced_unchecked(mode, count, ty, offset, primcount);
+    }
+
+    pub fn vertex_attrib_divisor(&self, gl: &mut WebGLRenderingContext, index: u32, divisor: u32) {
+        gl.vertex_attrib_divisor_unchecked(index, divisor);
+    }
 }
 
 #[cfg(test)]
@@ -1,6 +1,6 @@
 //! WebGL Module
 //!
-//! WebGL 1.0 implementation with resource pooling.
+//! WebGL 1.0 and 2.0 executed on the fos-render GL device, with resource pooling.
 
 pub mod context;
 pub mod framebuffer;
@@ -13,6 +13,7 @@ pub use context::{
     BufferUsage, TextureFormat,
     GL_VERTEX_SHADER, GL_FRAGMENT_SHADER, GL_ARRAY_BUFFER,
     GL_ELEMENT_ARRAY_BUFFER, GL_STATIC_DRAW, GL_TRIANGLES,
+    GL_COLOR_BUFFER_BIT, GL_DEPTH_BUFFER_BIT, GL_FLOAT, GL_NO_ERROR,
 };
 pub use framebuffer::{
     WebGLFramebuffer, WebGLRenderbuffer, RenderbufferFormat,
@@ -0,0 +1,21 @@
+//! Fragmented MP4 appended in pieces, as MSE source buffers receive it.
+//! The first byte picks the piece size.
+
+#![no_main]
+
+use fos_demux::fmp4::Fmp4Demuxer;
+use fos_demux::Demuxer;
+use libfuzzer_sys::fuzz_target;
+use std::time::Duration;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&chunk, data)) = data.split_first() else { return };
+    let mut demuxer = Fmp4Demuxer::new();
+    for piece in data.chunks(chunk as usize + 1) {
+        if demuxer.append(piece).is_err() {
+            demuxer.reset_parser();
+        }
+    }
+    let _ = demuxer.seek(Duration::ZERO);
+    while demuxer.read_packet().is_ok() {}
+});
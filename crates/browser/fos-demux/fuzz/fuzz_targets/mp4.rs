@@ -0,0 +1,18 @@
+//! Progressive MP4 files, as loaded by the media element
+
+#![no_main]
+
+use fos_demux::mp4::Mp4Demuxer;
+use fos_demux::Demuxer;
+use libfuzzer_sys::fuzz_target;
+use std::time::Duration;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut demuxer) = Mp4Demuxer::new(data.to_vec()) else { return };
+    for track in demuxer.video_track().into_iter().chain(demuxer.audio_track()) {
+        let _ = (track.codec_string(), track.description());
+    }
+    while demuxer.read_packet().is_ok() {}
+    let _ = demuxer.seek(Duration::from_millis(500));
+    while demuxer.read_packet().is_ok() {}
+});
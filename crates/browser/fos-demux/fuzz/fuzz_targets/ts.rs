@@ -0,0 +1,15 @@
+//! MPEG transport streams
+
+#![no_main]
+
+use fos_demux::ts::TsDemuxer;
+use fos_demux::Demuxer;
+use libfuzzer_sys::fuzz_target;
+use std::time::Duration;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut demuxer) = TsDemuxer::new(data.to_vec()) else { return };
+    while demuxer.read_packet().is_ok() {}
+    let _ = demuxer.seek(Duration::ZERO);
+    while demuxer.read_packet().is_ok() {}
+});
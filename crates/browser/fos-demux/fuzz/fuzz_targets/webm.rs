@@ -0,0 +1,28 @@
+//! WebM/Matroska, both as a whole file and appended in pieces (MSE).
+//! The first byte picks the piece size.
+
+#![no_main]
+
+use fos_demux::webm::WebMDemuxer;
+use fos_demux::Demuxer;
+use libfuzzer_sys::fuzz_target;
+use std::time::Duration;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&chunk, data)) = data.split_first() else { return };
+    if let Ok(mut demuxer) = WebMDemuxer::new(data.to_vec()) {
+        for track in demuxer.tracks() {
+            let _ = (track.codec_string(), track.description());
+        }
+        while demuxer.read_packet().is_ok() {}
+        let _ = demuxer.seek(Duration::from_millis(500));
+        while demuxer.read_packet().is_ok() {}
+    }
+
+    let mut demuxer = WebMDemuxer::streaming();
+    for piece in data.chunks(chunk as usize + 1) {
+        if demuxer.append(piece).is_err() {
+            demuxer.reset_parser();
+        }
+    }
+});
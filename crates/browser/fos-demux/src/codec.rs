@@ -0,0 +1,180 @@
+//! WebCodecs Decoder Configuration
+//!
+//! Codec strings (as registered for WebCodecs, following RFC 6381) and
+//! decoder `description` bytes for demuxed tracks, so their packets can be
+//! handed to a WebCodecs decoder as encoded chunks.
+
+use super::{CodecId, TrackInfo};
+
+impl TrackInfo {
+    /// Fully qualified codec string: `avc1.42E01E`, `vp09.00.10.08`,
+    /// `av01.0.04M.08`, `mp4a.40.2`, `opus`, ...
+    pub fn codec_string(&self) -> Option<String> {
+        let private = self.codec_private.as_slice();
+        Some(match self.codec {
+            CodecId::H264 => match private {
+                [1, profile, compatibility, level, ..] => format!("avc1.{profile:02X}{compatibility:02X}{level:02X}"),
+                _ => "avc1.42E01E".into(),
+            },
+            CodecId::H265 => hevc_codec_string(private).unwrap_or_else(|| "hvc1.1.6.L93.B0".into()),
+            CodecId::Vp8 => "vp8".into(),
+            CodecId::Vp9 => {
+                let (profile, level, depth) = vp9_parameters(private).unwrap_or((0, 10, 8));
+                format!("vp09.{profile:02}.{level:02}.{depth:02}")
+            }
+            CodecId::Av1 => match private {
+                [marker, profile_level, flags, ..] if marker & 0x80 != 0 => {
+                    let tier = if flags & 0x80 != 0 { 'H' } else { 'M' };
+                    let depth = match (flags & 0x40 != 0, flags & 0x20 != 0) {
+                        (true, true) => 12,
+                        (true, false) => 10,
+                        _ => 8,
+                    };
+                    format!("av01.{}.{:02}{tier}.{depth:02}", profile_level >> 5, profile_level & 0x1F)
+                }
+                _ => "av01.0.04M.08".into(),
+            },
+            CodecId::Aac => format!("mp4a.40.{}", audio_object_type(private).unwrap_or(2)),
+            CodecId::Mp3 => "mp3".into(),
+            CodecId::Opus => "opus".into(),
+            CodecId::Vorbis => "vorbis".into(),
+            CodecId::Flac => "flac".into(),
+            CodecId::Pcm | CodecId::WebVtt | CodecId::Subrip | CodecId::Unknown => return None,
+        })
+    }
+
+    /// Decoder `description`: the avcC/hvcC record, AudioSpecificConfig,
+    /// `OpusHead`, Vorbis headers or FLAC stream header. `None` for codecs
+    /// configured in band.
+    pub fn description(&self) -> Option<Vec<u8>> {
+        let private = self.codec_private.as_slice();
+        if private.is_empty() {
+            return None;
+        }
+        match self.codec {
+            CodecId::H264 | CodecId::H265 | CodecId::Aac | CodecId::Vorbis => Some(private.to_vec()),
+            CodecId::Opus if private.starts_with(b"OpusHead") => Some(private.to_vec()),
+            CodecId::Opus => opus_head(private),
+            CodecId::Flac if private.starts_with(b"fLaC") => Some(private.to_vec()),
+            // MP4 `dfLa` holds the metadata blocks without the stream marker
+            CodecId::Flac => Some([b"fLaC".as_slice(), private].concat()),
+            _ => None,
+        }
+    }
+}
+
+/// `hvc1.<space><profile>.<compatibility>.<tier><level>.<constraints>` from
+/// an hvcC record
+fn hevc_codec_string(hvcc: &[u8]) -> Option<String> {
+    if hvcc.len() < 13 {
+        return None;
+    }
+    let space = ["", "A", "B", "C"][(hvcc[1] >> 6) as usize];
+    let tier = if hvcc[1] & 0x20 != 0 { 'H' } else { 'L' };
+    let profile = hvcc[1] & 0x1F;
+    // Compatibility flags are written in reverse bit order
+    let compatibility = u32::from_be_bytes([hvcc[2], hvcc[3], hvcc[4], hvcc[5]]).reverse_bits();
+    let mut out = format!("hvc1.{space}{profile}.{compatibility:X}.{tier}{}", hvcc[12]);
+    let constraints = &hvcc[6..12];
+    let used = constraints.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    for byte in &constraints[..used.max(1)] {
+        out.push_str(&format!(".{byte:X}"));
+    }
+    Some(out)
+}
+
+/// (profile, level, bit depth) from an MP4 `vpcC` payload or a Matroska
+/// VP9 CodecPrivate (id, length 1, value triples)
+fn vp9_parameters(private: &[u8]) -> Option<(u8, u8, u8)> {
+    let is_feature_list = !private.is_empty()
+        && private.len().is_multiple_of(3)
+        && private.chunks(3).all(|f| (1..=4).contains(&f[0]) && f[1] == 1);
+    if is_feature_list {
+        let (mut profile, mut level, mut depth) = (0, 10, 8);
+        for feature in private.chunks(3) {
+            match feature[0] {
+                1 => profile = feature[2],
+                2 => level = feature[2],
+                3 => depth = feature[2],
+                _ => {}
+            }
+        }
+        return Some((profile, level, depth));
+    }
+    match private {
+        [profile, level, depth_chroma, ..] => Some((*profile, *level, depth_chroma >> 4)),
+        _ => None,
+    }
+}
+
+/// Audio object type at the start of an AudioSpecificConfig
+fn audio_object_type(config: &[u8]) -> Option<u8> {
+    let first = *config.first()?;
+    match first >> 3 {
+        31 => {
+            let second = *config.get(1)?;
+            Some(32 + (((first & 0x07) << 3) | (second >> 5)))
+        }
+        0 => None,
+        object_type => Some(object_type),
+    }
+}
+
+/// `OpusHead` from an MP4 `dOps` payload (big-endian fields, version 0)
+fn opus_head(dops: &[u8]) -> Option<Vec<u8>> {
+    if dops.len() < 11 || dops[0] != 0 {
+        return None;
+    }
+    let mut head = b"OpusHead".to_vec();
+    head.push(1);
+    head.push(dops[1]);
+    head.extend_from_slice(&u16::from_be_bytes([dops[2], dops[3]]).to_le_bytes());
+    head.extend_from_slice(&u32::from_be_bytes([dops[4], dops[5], dops[6], dops[7]]).to_le_bytes());
+    head.extend_from_slice(&i16::from_be_bytes([dops[8], dops[9]]).to_le_bytes());
+    // Channel mapping family, then the mapping table unchanged
+    head.extend_from_slice(&dops[10..]);
+    Some(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrackType;
+    use std::time::Duration;
+
+    fn track(codec: CodecId, codec_private: &[u8]) -> TrackInfo {
+        TrackInfo {
+            track_id: 1, track_type: TrackType::Video, codec, duration: Duration::ZERO, timescale: 1000,
+            width: None, height: None, frame_rate: None, sample_rate: None, channels: None,
+            codec_private: codec_private.to_vec(), encryption: None,
+        }
+    }
+
+    #[test]
+    fn test_video_codec_strings() {
+        assert_eq!(track(CodecId::H264, &[1, 0x64, 0, 0x28, 0xFF]).codec_string().unwrap(), "avc1.640028");
+        let mut hvcc = vec![1, 0x01, 0x60, 0, 0, 0, 0x90, 0, 0, 0, 0, 0, 93];
+        assert_eq!(track(CodecId::H265, &hvcc).codec_string().unwrap(), "hvc1.1.6.L93.90");
+        hvcc[1] = 0x22;
+        assert_eq!(track(CodecId::H265, &hvcc).codec_string().unwrap(), "hvc1.2.6.H93.90");
+        assert_eq!(track(CodecId::Vp9, &[2, 31, 0xA0, 1]).codec_string().unwrap(), "vp09.02.31.10");
+        assert_eq!(track(CodecId::Vp9, &[1, 1, 1, 2, 1, 40]).codec_string().unwrap(), "vp09.01.40.08");
+        assert_eq!(track(CodecId::Av1, &[0x81, 0x08, 0x40, 0]).codec_string().unwrap(), "av01.0.08M.10");
+        assert_eq!(track(CodecId::WebVtt, &[]).codec_string(), None);
+    }
+
+    #[test]
+    fn test_audio_descriptions() {
+        let aac = track(CodecId::Aac, &[0x12, 0x10]);
+        assert_eq!((aac.codec_string().unwrap(), aac.description()), ("mp4a.40.2".into(), Some(vec![0x12, 0x10])));
+        assert_eq!(track(CodecId::Aac, &[0xF8, 0xE0]).codec_string().unwrap(), "mp4a.40.39");
+
+        let dops = [0, 2, 0x01, 0x38, 0, 0, 0xBB, 0x80, 0, 0, 0];
+        let head = track(CodecId::Opus, &dops).description().unwrap();
+        assert_eq!(&head[..8], b"OpusHead");
+        assert_eq!(&head[8..], &[1, 2, 0x38, 0x01, 0x80, 0xBB, 0, 0, 0, 0, 0]);
+
+        assert_eq!(&track(CodecId::Flac, &[0x80, 0, 0, 34]).description().unwrap()[..5], b"fLaC\x80");
+        assert_eq!(track(CodecId::Vp8, &[1]).description(), None);
+    }
+}
@@ -0,0 +1,75 @@
+//! Test Fixtures
+//!
+//! Builders for small ISO BMFF files and segments, shared with the tests of
+//! dependent crates (feature `fixtures`) and used to seed fuzzing.
+
+use crate::mp4::SAMPLE_NON_SYNC;
+
+/// Plain box around `payload`
+pub fn mp4_box(name: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+    out.extend_from_slice(name);
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Full box: version and flags, then `payload`
+pub fn full_box(name: &[u8; 4], version: u8, flags: u32, payload: &[u8]) -> Vec<u8> {
+    let mut body = (flags | (version as u32) << 24).to_be_bytes().to_vec();
+    body.extend_from_slice(payload);
+    mp4_box(name, &body)
+}
+
+/// Big-endian `u32` fields
+pub fn u32s(values: &[u32]) -> Vec<u8> { values.iter().flat_map(|v| v.to_be_bytes()).collect() }
+
+/// moov with one 320x240 avc1 track (id 1, timescale 1000); `stbl_extra`
+/// is appended to an otherwise empty sample table
+pub fn init_segment(stbl_extra: &[u8], fragmented: bool) -> Vec<u8> {
+    init_segment_with(b"avc1", &[], &[], stbl_extra, fragmented)
+}
+
+/// `init_segment` with another sample entry type, extra sample entry
+/// children and extra `moov` children
+pub fn init_segment_with(fourcc: &[u8; 4], entry_extra: &[u8], moov_extra: &[u8], stbl_extra: &[u8], fragmented: bool) -> Vec<u8> {
+    let mut entry = vec![0u8; 78];
+    entry[6..8].copy_from_slice(&1u16.to_be_bytes());
+    entry[24..26].copy_from_slice(&320u16.to_be_bytes());
+    entry[26..28].copy_from_slice(&240u16.to_be_bytes());
+    entry.extend(mp4_box(b"avcC", &[1, 0x42, 0, 0x1E, 0xFF, 0xE0, 0]));
+    entry.extend_from_slice(entry_extra);
+    let stsd = full_box(b"stsd", 0, 0, &[u32s(&[1]), mp4_box(fourcc, &entry)].concat());
+    let stbl = mp4_box(b"stbl", &[stsd, stbl_extra.to_vec()].concat());
+    let minf = mp4_box(b"minf", &stbl);
+    let hdlr = full_box(b"hdlr", 0, 0, &[u32s(&[0]), b"vide".to_vec(), vec![0; 13]].concat());
+    let mdhd = full_box(b"mdhd", 0, 0, &u32s(&[0, 0, 1000, 3000, 0]));
+    let mdia = mp4_box(b"mdia", &[mdhd, hdlr, minf].concat());
+    let tkhd = full_box(b"tkhd", 0, 3, &u32s(&[0, 0, 1, 0, 3000]));
+    let trak = mp4_box(b"trak", &[tkhd, mdia].concat());
+    let mvhd = full_box(b"mvhd", 0, 0, &u32s(&[0, 0, 1000, 3000]));
+    let mut moov = [mvhd, trak, moov_extra.to_vec()].concat();
+    if fragmented {
+        moov.extend(mp4_box(b"mvex", &full_box(b"trex", 0, 0, &u32s(&[1, 1, 100, 0, SAMPLE_NON_SYNC]))));
+    }
+    [mp4_box(b"ftyp", b"isom\0\0\0\0"), mp4_box(b"moov", &moov)].concat()
+}
+
+/// moof + mdat carrying `sizes.len()` samples starting at `base_dts`
+pub fn media_segment(base_dts: u32, sizes: &[u32]) -> Vec<u8> {
+    media_segment_with(base_dts, sizes, &[])
+}
+
+pub fn media_segment_with(base_dts: u32, sizes: &[u32], traf_extra: &[u8]) -> Vec<u8> {
+    let build = |data_offset: u32| {
+        let tfhd = full_box(b"tfhd", 0, 0x02_0000, &u32s(&[1]));
+        let tfdt = full_box(b"tfdt", 0, 0, &u32s(&[base_dts]));
+        let mut trun_body = u32s(&[sizes.len() as u32, data_offset, 0]);
+        trun_body.extend(u32s(sizes));
+        let trun = full_box(b"trun", 0, 0x001 | 0x004 | 0x200, &trun_body);
+        mp4_box(b"moof", &mp4_box(b"traf", &[tfhd, tfdt, trun, traf_extra.to_vec()].concat()))
+    };
+    let moof_len = build(0).len() as u32;
+    let moof = build(moof_len + 8);
+    let payload: Vec<u8> = sizes.iter().enumerate().flat_map(|(i, &s)| vec![i as u8; s as usize]).collect();
+    [moof, mp4_box(b"mdat", &payload)].concat()
+}
@@ -0,0 +1,171 @@
+//! Fragmented MP4 Parser
+//!
+//! Parser for fragmented MP4 (fMP4) used in DASH and MSE. Bytes may arrive
+//! in arbitrary chunks; boxes are only consumed once complete.
+
+use super::{Demuxer, DemuxerResult, DemuxerError, TrackInfo, Packet, InitData};
+use super::mp4::{self, Mp4Demuxer, Mp4Track, BoxType};
+use std::time::Duration;
+
+/// Fragmented MP4 Demuxer
+#[derive(Debug)]
+pub struct Fmp4Demuxer {
+    init_segment: Option<Mp4Demuxer>,
+    tracks: Vec<Mp4Track>,
+    /// Bytes not yet forming a complete box (or moof + mdat pair)
+    pending: Vec<u8>,
+    packets: Vec<Packet>,
+    pos: usize,
+    eof: bool,
+    init_data: Vec<InitData>,
+}
+
+impl Fmp4Demuxer {
+    pub fn new() -> Self {
+        Self { init_segment: None, tracks: Vec::new(), pending: Vec::new(), packets: Vec::new(), pos: 0, eof: false, init_data: Vec::new() }
+    }
+
+    /// Set initialization segment (moov box)
+    pub fn set_init_segment(&mut self, data: Vec<u8>) -> DemuxerResult<()> {
+        let init = Mp4Demuxer::new(data)?;
+        self.tracks = init.tracks().to_vec();
+        self.add_init_data(init.init_data().to_vec());
+        self.init_segment = Some(init);
+        Ok(())
+    }
+
+    /// Append media segment (moof + mdat), queueing its samples for `read_packet`
+    pub fn append_segment(&mut self, data: Vec<u8>) -> DemuxerResult<()> {
+        let mut packets = self.append(&data)?;
+        self.packets.append(&mut packets);
+        self.eof = false;
+        Ok(())
+    }
+
+    /// Record init data not seen before; repeated `pssh` boxes fire no new `encrypted` event
+    fn add_init_data(&mut self, init_data: Vec<InitData>) {
+        for data in init_data {
+            if !self.init_data.contains(&data) { self.init_data.push(data); }
+        }
+    }
+
+    /// Whether an initialization segment has been parsed
+    pub fn has_init_segment(&self) -> bool { self.init_segment.is_some() }
+
+    /// Feed raw bytes, returning the samples of every fragment completed by
+    /// them. Init segments found in the stream replace the current one.
+    pub fn append(&mut self, data: &[u8]) -> DemuxerResult<Vec<Packet>> {
+        self.pending.extend_from_slice(data);
+        let mut packets = Vec::new();
+        let mut pos = 0;
+
+        while let Some((box_type, payload, end)) = Self::complete_box(&self.pending, pos) {
+            match box_type {
+                BoxType::MOOV => {
+                    let moov = self.pending[pos..end].to_vec();
+                    self.set_init_segment(moov)?;
+                }
+                BoxType::MOOF => {
+                    if self.init_segment.is_none() {
+                        return Err(DemuxerError::InvalidContainer("Media segment before init segment".into()));
+                    }
+                    // Samples live in the following mdat; wait until it is complete
+                    let Some((next, _, mdat_end)) = Self::complete_box(&self.pending, end) else { break };
+                    let fragment_end = if next == BoxType::MDAT { mdat_end } else { end };
+                    let samples = mp4::parse_moof(&self.pending, pos, end, &mut self.tracks);
+                    self.add_init_data(mp4::pssh_boxes(&self.pending, payload, end));
+                    let start = packets.len();
+                    for s in samples {
+                        let (offset, size) = (s.offset as usize, s.size as usize);
+                        let Some(data) = self.pending.get(offset..offset.saturating_add(size)) else {
+                            return Err(DemuxerError::InvalidContainer("Sample outside of mdat".into()));
+                        };
+                        packets.push(s.packet(data.to_vec()));
+                    }
+                    packets[start..].sort_by_key(|p| p.dts);
+                    pos = fragment_end;
+                    continue;
+                }
+                // ftyp, styp, sidx, emsg, free and stray mdat boxes carry no samples
+                _ => {}
+            }
+            pos = end;
+        }
+        self.pending.drain(..pos);
+        Ok(packets)
+    }
+
+    /// Drop partially received data, e.g. on `SourceBuffer.abort()`
+    pub fn reset_parser(&mut self) { self.pending.clear(); }
+
+    fn complete_box(data: &[u8], pos: usize) -> Option<(BoxType, usize, usize)> {
+        // A zero size means "to end of file", which is never known while streaming
+        if mp4::read_u32(data, pos) == 0 { return None; }
+        mp4::box_header(data, pos, data.len()).filter(|&(_, _, end)| end <= data.len())
+    }
+}
+
+impl Default for Fmp4Demuxer { fn default() -> Self { Self::new() } }
+
+impl Demuxer for Fmp4Demuxer {
+    fn duration(&self) -> Option<Duration> { self.init_segment.as_ref().and_then(|d| d.duration()) }
+    fn video_track(&self) -> Option<&TrackInfo> { self.init_segment.as_ref().and_then(|d| d.video_track()) }
+    fn audio_track(&self) -> Option<&TrackInfo> { self.init_segment.as_ref().and_then(|d| d.audio_track()) }
+
+    fn read_packet(&mut self) -> DemuxerResult<Packet> {
+        match self.packets.get(self.pos) {
+            Some(packet) => { self.pos += 1; Ok(packet.clone()) }
+            None => { self.eof = true; Err(DemuxerError::EndOfStream) }
+        }
+    }
+
+    fn seek(&mut self, position: Duration) -> DemuxerResult<()> {
+        let video = self.video_track().map(|t| t.track_id);
+        self.pos = mp4::seek_index(&self.packets, video, position, |p| (p.track_id, p.pts, p.dts, p.is_key));
+        self.eof = false;
+        Ok(())
+    }
+
+    fn is_eof(&self) -> bool { self.eof }
+    fn init_data(&self) -> &[InitData] { &self.init_data }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{init_segment, media_segment};
+
+    #[test]
+    fn test_fmp4() { let d = Fmp4Demuxer::new(); assert!(d.init_segment.is_none()); }
+
+    #[test]
+    fn test_chunked_append() {
+        let stream = [init_segment(&[], true), media_segment(0, &[3, 2]), media_segment(200, &[4])].concat();
+        let mut d = Fmp4Demuxer::new();
+        let mut packets = Vec::new();
+        for chunk in stream.chunks(7) {
+            packets.extend(d.append(chunk).unwrap());
+        }
+        assert!(d.has_init_segment());
+        assert_eq!(d.video_track().unwrap().width, Some(320));
+        assert_eq!(packets.iter().map(|p| p.data.len()).collect::<Vec<_>>(), vec![3, 2, 4]);
+        assert_eq!(packets[2].pts, Duration::from_millis(200));
+        assert!(d.pending.is_empty());
+    }
+
+    #[test]
+    fn test_media_before_init() {
+        let mut d = Fmp4Demuxer::new();
+        assert!(d.append(&media_segment(0, &[1])).is_err());
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut d = Fmp4Demuxer::new();
+        d.append_segment(init_segment(&[], true)).unwrap();
+        d.append_segment(media_segment(0, &[1, 1])).unwrap();
+        d.append_segment(media_segment(200, &[2, 2])).unwrap();
+        d.seek(Duration::from_millis(300)).unwrap();
+        assert_eq!(d.read_packet().unwrap().pts, Duration::from_millis(200));
+    }
+}
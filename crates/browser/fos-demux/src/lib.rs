@@ -0,0 +1,268 @@
+//! fOS Demux
+//!
+//! Container parsers shared by the media element, Media Source Extensions
+//! and WebCodecs: ISO BMFF (MP4, fragmented MP4), Matroska/WebM (EBML) and
+//! MPEG-TS. Every parser turns untrusted bytes into timed encoded samples
+//! ([`Packet`]) and track descriptions ([`TrackInfo`]); none of them may
+//! panic on malformed input, which the fuzz targets in `fuzz/` check.
+
+pub mod mp4;
+pub mod webm;
+pub mod mkv;
+pub mod ts;
+pub mod fmp4;
+pub mod codec;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+
+use std::time::Duration;
+
+/// Demuxer trait
+pub trait Demuxer: Send {
+    /// Get duration of media
+    fn duration(&self) -> Option<Duration>;
+    
+    /// Get video track info
+    fn video_track(&self) -> Option<&TrackInfo>;
+    
+    /// Get audio track info
+    fn audio_track(&self) -> Option<&TrackInfo>;
+    
+    /// Read next packet
+    fn read_packet(&mut self) -> DemuxerResult<Packet>;
+    
+    /// Seek to position
+    fn seek(&mut self, position: Duration) -> DemuxerResult<()>;
+    
+    /// Check if at end of stream
+    fn is_eof(&self) -> bool;
+    
+    /// Protection init data seen so far (`pssh` boxes), for `encrypted` events
+    fn init_data(&self) -> &[InitData] { &[] }
+}
+
+/// Track information
+#[derive(Debug, Clone)]
+pub struct TrackInfo {
+    pub track_id: u32,
+    pub track_type: TrackType,
+    pub codec: CodecId,
+    pub duration: Duration,
+    pub timescale: u32,
+    /// For video
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    /// For audio  
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    /// Codec-specific data (SPS/PPS, AudioSpecificConfig, etc.)
+    pub codec_private: Vec<u8>,
+    /// Common Encryption defaults for protected tracks
+    pub encryption: Option<TrackEncryption>,
+}
+
+/// Track protection defaults (`schm` + `tenc`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackEncryption {
+    /// Protection scheme: `cenc`, `cbcs`, ...
+    pub scheme: [u8; 4],
+    pub default_key_id: [u8; 16],
+    /// Per-sample IV size: 0, 8 or 16
+    pub default_iv_size: u8,
+}
+
+/// Per-sample encryption parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleEncryption {
+    pub scheme: [u8; 4],
+    pub key_id: [u8; 16],
+    pub iv: Vec<u8>,
+    /// Clear/encrypted byte ranges; empty when the whole sample is encrypted
+    pub subsamples: Vec<Subsample>,
+}
+
+/// Subsample: clear bytes followed by encrypted bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subsample {
+    pub clear_bytes: u32,
+    pub encrypted_bytes: u32,
+}
+
+/// Init data for the `encrypted` event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitData {
+    /// `cenc`, `keyids` or `webm`
+    pub init_data_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Track type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackType { Video, Audio, Subtitle, Data }
+
+/// Codec identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecId {
+    H264, H265, Vp8, Vp9, Av1,
+    Aac, Mp3, Opus, Vorbis, Flac, Pcm,
+    WebVtt, Subrip,
+    Unknown,
+}
+
+/// Demuxed packet
+#[derive(Debug, Clone)]
+pub struct Packet {
+    pub track_id: u32,
+    pub pts: Duration,
+    pub dts: Duration,
+    pub duration: Duration,
+    pub is_key: bool,
+    pub data: Vec<u8>,
+    /// Set when the sample must be decrypted before decoding
+    pub encryption: Option<SampleEncryption>,
+}
+
+/// Demuxer result type
+pub type DemuxerResult<T> = Result<T, DemuxerError>;
+
+/// Demuxer error
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum DemuxerError {
+    #[error("Invalid container: {0}")]
+    InvalidContainer(String),
+    #[error("Unsupported feature: {0}")]
+    Unsupported(String),
+    #[error("Need more data")]
+    NeedMoreData,
+    #[error("End of stream")]
+    EndOfStream,
+    #[error("IO error: {0}")]
+    IoError(String),
+}
+
+/// Container format detection
+pub fn detect_format(data: &[u8]) -> Option<ContainerFormat> {
+    if data.len() < 12 { return None; }
+    
+    // MP4/MOV: ftyp box or moov box
+    if &data[4..8] == b"ftyp" || &data[4..8] == b"moov" || &data[4..8] == b"mdat" {
+        return Some(ContainerFormat::Mp4);
+    }
+    if &data[4..8] == b"moof" || &data[4..8] == b"styp" {
+        return Some(ContainerFormat::FragmentedMp4);
+    }
+    
+    // WebM/MKV: EBML header
+    if data[0..4] == [0x1A, 0x45, 0xDF, 0xA3] {
+        // Check DocType for webm vs matroska
+        if data.len() > 30 {
+            let s = String::from_utf8_lossy(&data[..data.len().min(40)]);
+            if s.contains("webm") { return Some(ContainerFormat::WebM); }
+            if s.contains("matroska") { return Some(ContainerFormat::Mkv); }
+        }
+        return Some(ContainerFormat::Mkv);
+    }
+    
+    // MPEG-TS: sync byte 0x47 every 188 bytes
+    if data[0] == 0x47 && (data.len() < 188 || data[188] == 0x47) {
+        return Some(ContainerFormat::MpegTs);
+    }
+    
+    None
+}
+
+/// Container format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerFormat { Mp4, WebM, Mkv, MpegTs, FragmentedMp4 }
+
+impl ContainerFormat {
+    /// Whether the container can carry `codec`
+    pub fn can_carry(&self, codec: CodecId) -> bool {
+        use CodecId::*;
+        match self {
+            Self::Mp4 | Self::FragmentedMp4 => matches!(codec, H264 | H265 | Vp9 | Av1 | Aac | Mp3 | Opus | Flac | WebVtt),
+            Self::WebM => matches!(codec, Vp8 | Vp9 | Av1 | Opus | Vorbis | WebVtt),
+            Self::Mkv => codec != Unknown,
+            Self::MpegTs => matches!(codec, H264 | H265 | Aac | Mp3),
+        }
+    }
+}
+
+/// Container for a MIME type essence (`video/mp4`, `audio/webm`, ...)
+pub fn format_for_mime(essence: &str) -> Option<ContainerFormat> {
+    match essence.trim().to_ascii_lowercase().as_str() {
+        "video/mp4" | "audio/mp4" | "video/quicktime" => Some(ContainerFormat::Mp4),
+        "video/webm" | "audio/webm" => Some(ContainerFormat::WebM),
+        "video/x-matroska" | "audio/x-matroska" => Some(ContainerFormat::Mkv),
+        "video/mp2t" => Some(ContainerFormat::MpegTs),
+        _ => None,
+    }
+}
+
+/// Codec named by an RFC 6381 `codecs` entry (`avc1.42E01E`, `vp09.00.10.08`, `opus`, ...)
+pub fn codec_from_string(codec: &str) -> Option<CodecId> {
+    let codec = codec.trim();
+    let (fourcc, rest) = codec.split_once('.').unwrap_or((codec, ""));
+    Some(match fourcc {
+        "avc1" | "avc3" => CodecId::H264,
+        "hvc1" | "hev1" => CodecId::H265,
+        "vp8" | "vp08" => CodecId::Vp8,
+        "vp9" | "vp09" => CodecId::Vp9,
+        "av01" => CodecId::Av1,
+        "mp4a" => match rest.split('.').next().unwrap_or("").to_ascii_lowercase().as_str() {
+            "40" | "66" | "67" | "68" => CodecId::Aac,
+            "69" | "6b" => CodecId::Mp3,
+            _ => return None,
+        },
+        "mp3" => CodecId::Mp3,
+        "opus" | "Opus" => CodecId::Opus,
+        "vorbis" => CodecId::Vorbis,
+        "flac" | "fLaC" => CodecId::Flac,
+        "wvtt" => CodecId::WebVtt,
+        _ => return None,
+    })
+}
+
+impl CodecId {
+    pub fn is_video(&self) -> bool { matches!(self, Self::H264 | Self::H265 | Self::Vp8 | Self::Vp9 | Self::Av1) }
+}
+
+/// Split a MIME type into its lowercase essence and `codecs` parameter entries
+pub fn parse_mime(mime: &str) -> (String, Vec<String>) {
+    let mut parts = mime.split(';');
+    let essence = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+    let codecs = parts
+        .filter_map(|p| p.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("codecs"))
+        .map(|(_, value)| {
+            value.trim().trim_matches('"').split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect()
+        })
+        .unwrap_or_default();
+    (essence, codecs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_detect_mp4() {
+        let data = [0, 0, 0, 20, b'f', b't', b'y', b'p', b'm', b'p', b'4', b'2'];
+        assert_eq!(detect_format(&data), Some(ContainerFormat::Mp4));
+    }
+    
+    #[test]
+    fn test_parse_mime() {
+        let (essence, codecs) = parse_mime("Video/MP4; codecs=\"avc1.42E01E, mp4a.40.2\"");
+        assert_eq!(essence, "video/mp4");
+        assert_eq!(codecs.iter().map(|c| codec_from_string(c)).collect::<Vec<_>>(), vec![Some(CodecId::H264), Some(CodecId::Aac)]);
+        assert_eq!(format_for_mime(&essence), Some(ContainerFormat::Mp4));
+    }
+    
+    #[test]    
+    fn test_detect_ebml() {
+        let data = [0x1A, 0x45, 0xDF, 0xA3, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(detect_format(&data), Some(ContainerFormat::Mkv));
+    }
+}
@@ -0,0 +1,744 @@
+//! MP4/MOV Parser
+//!
+//! ISO Base Media File Format (ISOBMFF) parser for MP4 and MOV files.
+//! Handles both progressive files (`moov` sample tables) and fragmented
+//! files (`moof`/`traf`/`trun`), which share the sample model below.
+//! Common Encryption metadata (`pssh`, `tenc`, `senc`) is attached to the
+//! samples it protects.
+
+use super::{
+    Demuxer, DemuxerResult, DemuxerError, TrackInfo, TrackType, CodecId, Packet,
+    InitData, TrackEncryption, SampleEncryption, Subsample,
+};
+use std::time::Duration;
+
+/// MP4 Box types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoxType(pub [u8; 4]);
+
+impl BoxType {
+    pub const FTYP: Self = Self(*b"ftyp");
+    pub const MOOV: Self = Self(*b"moov");
+    pub const MVHD: Self = Self(*b"mvhd");
+    pub const TRAK: Self = Self(*b"trak");
+    pub const TKHD: Self = Self(*b"tkhd");
+    pub const EDTS: Self = Self(*b"edts");
+    pub const ELST: Self = Self(*b"elst");
+    pub const MDIA: Self = Self(*b"mdia");
+    pub const MDHD: Self = Self(*b"mdhd");
+    pub const HDLR: Self = Self(*b"hdlr");
+    pub const MINF: Self = Self(*b"minf");
+    pub const STBL: Self = Self(*b"stbl");
+    pub const STSD: Self = Self(*b"stsd");
+    pub const STTS: Self = Self(*b"stts");
+    pub const STSS: Self = Self(*b"stss");
+    pub const STSC: Self = Self(*b"stsc");
+    pub const STSZ: Self = Self(*b"stsz");
+    pub const STCO: Self = Self(*b"stco");
+    pub const CO64: Self = Self(*b"co64");
+    pub const CTTS: Self = Self(*b"ctts");
+    pub const MVEX: Self = Self(*b"mvex");
+    pub const TREX: Self = Self(*b"trex");
+    pub const MOOF: Self = Self(*b"moof");
+    pub const TRAF: Self = Self(*b"traf");
+    pub const TFHD: Self = Self(*b"tfhd");
+    pub const TFDT: Self = Self(*b"tfdt");
+    pub const TRUN: Self = Self(*b"trun");
+    pub const MDAT: Self = Self(*b"mdat");
+    pub const AVCC: Self = Self(*b"avcC");
+    pub const HVCC: Self = Self(*b"hvcC");
+    pub const VP09: Self = Self(*b"vp09");
+    pub const VPCC: Self = Self(*b"vpcC");
+    pub const AV1C: Self = Self(*b"av1C");
+    pub const ESDS: Self = Self(*b"esds");
+    pub const DOPS: Self = Self(*b"dOps");
+    pub const DFLA: Self = Self(*b"dfLa");
+    pub const SINF: Self = Self(*b"sinf");
+    pub const FRMA: Self = Self(*b"frma");
+    pub const SCHM: Self = Self(*b"schm");
+    pub const SCHI: Self = Self(*b"schi");
+    pub const TENC: Self = Self(*b"tenc");
+    pub const SENC: Self = Self(*b"senc");
+    pub const PSSH: Self = Self(*b"pssh");
+}
+
+/// MP4 Box header
+#[derive(Debug, Clone)]
+pub struct Box { pub box_type: BoxType, pub size: u64, pub offset: u64 }
+
+pub fn read_u16(data: &[u8], pos: usize) -> u16 {
+    if pos + 2 > data.len() { return 0; }
+    u16::from_be_bytes([data[pos], data[pos + 1]])
+}
+
+pub fn read_u32(data: &[u8], pos: usize) -> u32 {
+    if pos + 4 > data.len() { return 0; }
+    u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+pub fn read_u64(data: &[u8], pos: usize) -> u64 {
+    if pos + 8 > data.len() { return 0; }
+    u64::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3], data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]])
+}
+
+/// Header of the box at `pos`: (type, payload start, box end).
+/// Returns None if the header is truncated or the size is invalid.
+pub(crate) fn box_header(data: &[u8], pos: usize, limit: usize) -> Option<(BoxType, usize, usize)> {
+    if pos + 8 > limit { return None; }
+    let size = read_u32(data, pos) as u64;
+    let box_type = BoxType([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]);
+    let (size, header) = match size {
+        0 => ((limit - pos) as u64, 8),
+        1 if pos + 16 <= limit => (read_u64(data, pos + 8), 16),
+        1 => return None,
+        s => (s, 8),
+    };
+    if size < header as u64 { return None; }
+    let end = pos.checked_add(size as usize)?;
+    Some((box_type, pos + header, end))
+}
+
+/// Iterate the child boxes inside `start..end`, stopping at truncated boxes
+pub(crate) fn children(data: &[u8], start: usize, end: usize) -> impl Iterator<Item = (BoxType, usize, usize)> + '_ {
+    let end = end.min(data.len());
+    let mut pos = start;
+    std::iter::from_fn(move || {
+        let (box_type, payload, box_end) = box_header(data, pos, end)?;
+        if box_end > end { return None; }
+        pos = box_end;
+        Some((box_type, payload, box_end))
+    })
+}
+
+fn find_child(data: &[u8], start: usize, end: usize, wanted: BoxType) -> Option<(usize, usize)> {
+    children(data, start, end).find(|(t, _, _)| *t == wanted).map(|(_, p, e)| (p, e))
+}
+
+/// Convert media timescale ticks to a duration, clamping negatives to zero
+pub(crate) fn ticks_to_duration(ticks: i64, timescale: u32) -> Duration {
+    if ticks <= 0 || timescale == 0 { return Duration::ZERO; }
+    Duration::from_nanos((ticks as u128 * 1_000_000_000 / timescale as u128) as u64)
+}
+
+/// Per-track state needed to expand sample tables and track fragments
+#[derive(Debug, Clone)]
+pub(crate) struct Mp4Track {
+    pub info: TrackInfo,
+    /// Media time of the first edit (subtracted from timestamps)
+    pub edit_offset: i64,
+    /// `trex` defaults: duration, size, flags
+    pub default_duration: u32,
+    pub default_size: u32,
+    pub default_flags: u32,
+    /// Decode time following the last parsed fragment
+    pub next_dts: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SampleInfo {
+    pub track_id: u32, pub offset: u64, pub size: u32, pub pts: Duration, pub dts: Duration, pub duration: Duration, pub is_key: bool,
+    pub encryption: Option<SampleEncryption>,
+}
+
+impl SampleInfo {
+    fn new(track: &Mp4Track, offset: u64, size: u32, dts: u64, cts_offset: i64, duration: u32, is_key: bool) -> Self {
+        let ts = track.info.timescale;
+        Self {
+            track_id: track.info.track_id, offset, size,
+            pts: ticks_to_duration((dts as i64).saturating_add(cts_offset).saturating_sub(track.edit_offset), ts),
+            dts: ticks_to_duration((dts as i64).saturating_sub(track.edit_offset), ts),
+            duration: ticks_to_duration(duration as i64, ts),
+            is_key,
+            encryption: None,
+        }
+    }
+
+    pub fn packet(&self, data: Vec<u8>) -> Packet {
+        Packet {
+            track_id: self.track_id, pts: self.pts, dts: self.dts, duration: self.duration, is_key: self.is_key,
+            data, encryption: self.encryption.clone(),
+        }
+    }
+}
+
+/// `pssh` boxes directly inside `start..end`, as `cenc` init data
+pub(crate) fn pssh_boxes(data: &[u8], start: usize, end: usize) -> Vec<InitData> {
+    children(data, start, end)
+        .filter(|(t, _, _)| *t == BoxType::PSSH)
+        .map(|(_, payload, box_end)| InitData { init_data_type: "cenc".into(), data: data[payload - 8..box_end].to_vec() })
+        .collect()
+}
+
+/// Per-sample IVs and subsamples from a `senc` box
+fn parse_senc(data: &[u8], senc: usize, senc_end: usize, protection: &TrackEncryption, samples: &mut [SampleInfo]) {
+    let flags = read_u32(data, senc) & 0xFF_FFFF;
+    let count = read_u32(data, senc + 4) as usize;
+    let iv_size = protection.default_iv_size as usize;
+    let mut pos = senc + 8;
+    for sample in samples.iter_mut().take(count) {
+        let Some(iv) = data.get(pos..pos + iv_size).filter(|_| pos + iv_size <= senc_end) else { return };
+        pos += iv_size;
+        let mut subsamples = Vec::new();
+        if flags & 0x02 != 0 {
+            if pos + 2 > senc_end { return; }
+            let n = read_u16(data, pos) as usize;
+            pos += 2;
+            if pos + n * 6 > senc_end { return; }
+            subsamples = (0..n).map(|i| Subsample {
+                clear_bytes: read_u16(data, pos + i * 6) as u32,
+                encrypted_bytes: read_u32(data, pos + i * 6 + 2),
+            }).collect();
+            pos += n * 6;
+        }
+        sample.encryption = Some(SampleEncryption {
+            scheme: protection.scheme,
+            key_id: protection.default_key_id,
+            iv: iv.to_vec(),
+            subsamples,
+        });
+    }
+}
+
+/// Sample flags: sample_is_non_sync_sample
+pub(crate) const SAMPLE_NON_SYNC: u32 = 0x0001_0000;
+
+/// Parse a `moof` box whose first byte is at `moof_start`, returning samples
+/// with absolute offsets into `data`
+pub(crate) fn parse_moof(data: &[u8], moof_start: usize, moof_end: usize, tracks: &mut [Mp4Track]) -> Vec<SampleInfo> {
+    let mut samples = Vec::new();
+    let Some((_, payload, _)) = box_header(data, moof_start, moof_end) else { return samples };
+    // Without explicit base offsets, each fragment's data follows the previous one
+    let mut implicit_base = moof_start as u64;
+
+    for (box_type, traf, traf_end) in children(data, payload, moof_end) {
+        if box_type != BoxType::TRAF { continue; }
+        let Some((tfhd, _)) = find_child(data, traf, traf_end, BoxType::TFHD) else { continue };
+        let tf_flags = read_u32(data, tfhd) & 0xFF_FFFF;
+        let track_id = read_u32(data, tfhd + 4);
+        let Some(track) = tracks.iter_mut().find(|t| t.info.track_id == track_id) else { continue };
+
+        let mut pos = tfhd + 8;
+        let mut base = implicit_base;
+        if tf_flags & 0x01 != 0 { base = read_u64(data, pos); pos += 8; }
+        else if tf_flags & 0x02_0000 != 0 { base = moof_start as u64; }
+        if tf_flags & 0x02 != 0 { pos += 4; } // sample_description_index
+        let mut default_duration = track.default_duration;
+        let mut default_size = track.default_size;
+        let mut default_flags = track.default_flags;
+        if tf_flags & 0x08 != 0 { default_duration = read_u32(data, pos); pos += 4; }
+        if tf_flags & 0x10 != 0 { default_size = read_u32(data, pos); pos += 4; }
+        if tf_flags & 0x20 != 0 { default_flags = read_u32(data, pos); }
+
+        let mut dts = match find_child(data, traf, traf_end, BoxType::TFDT) {
+            Some((tfdt, _)) if data[tfdt] == 1 => read_u64(data, tfdt + 4),
+            Some((tfdt, _)) => read_u32(data, tfdt + 4) as u64,
+            None => track.next_dts,
+        };
+        let mut data_end = base;
+        let first_sample = samples.len();
+
+        for (box_type, trun, trun_end) in children(data, traf, traf_end) {
+            if box_type != BoxType::TRUN { continue; }
+            let version = data[trun];
+            let flags = read_u32(data, trun) & 0xFF_FFFF;
+            let count = read_u32(data, trun + 4);
+            let mut pos = trun + 8;
+            let mut offset = data_end;
+            if flags & 0x001 != 0 { offset = base.wrapping_add_signed(read_u32(data, pos) as i32 as i64); pos += 4; }
+            let first_flags = if flags & 0x004 != 0 { pos += 4; Some(read_u32(data, pos - 4)) } else { None };
+            let per_sample = [0x100, 0x200, 0x400, 0x800].iter().filter(|&&f| flags & f != 0).count() * 4;
+            if (count as usize).saturating_mul(per_sample) > trun_end.saturating_sub(pos) { break; }
+            // Without per-sample fields the count is unchecked; every sample
+            // still needs a byte of its own somewhere in the data
+            if count as usize > data.len() { break; }
+
+            for i in 0..count {
+                let duration = if flags & 0x100 != 0 { pos += 4; read_u32(data, pos - 4) } else { default_duration };
+                let size = if flags & 0x200 != 0 { pos += 4; read_u32(data, pos - 4) } else { default_size };
+                let mut sample_flags = if flags & 0x400 != 0 { pos += 4; read_u32(data, pos - 4) } else { default_flags };
+                if i == 0 { if let Some(f) = first_flags { sample_flags = f; } }
+                let cts = if flags & 0x800 != 0 {
+                    pos += 4;
+                    let raw = read_u32(data, pos - 4);
+                    if version == 0 { raw as i64 } else { raw as i32 as i64 }
+                } else { 0 };
+                let is_key = track.info.track_type != TrackType::Video || sample_flags & SAMPLE_NON_SYNC == 0;
+                samples.push(SampleInfo::new(track, offset, size, dts, cts, duration, is_key));
+                offset = offset.saturating_add(size as u64);
+                dts = dts.saturating_add(duration as u64);
+            }
+            data_end = offset;
+        }
+        if let (Some(protection), Some((senc, senc_end))) = (&track.info.encryption, find_child(data, traf, traf_end, BoxType::SENC)) {
+            parse_senc(data, senc, senc_end, protection, &mut samples[first_sample..]);
+        }
+        track.next_dts = dts;
+        implicit_base = data_end;
+    }
+    samples
+}
+
+/// MP4 Demuxer
+#[derive(Debug)]
+pub struct Mp4Demuxer {
+    data: Vec<u8>,
+    duration: Duration,
+    timescale: u32,
+    tracks: Vec<Mp4Track>,
+    video_track: Option<TrackInfo>,
+    audio_track: Option<TrackInfo>,
+    samples: Vec<SampleInfo>,
+    current_sample: usize,
+    eof: bool,
+    init_data: Vec<InitData>,
+}
+
+impl Mp4Demuxer {
+    pub fn new(data: Vec<u8>) -> DemuxerResult<Self> {
+        let mut demuxer = Self {
+            data, duration: Duration::ZERO, timescale: 1000, tracks: Vec::new(),
+            video_track: None, audio_track: None, samples: Vec::new(), current_sample: 0, eof: false,
+            init_data: Vec::new(),
+        };
+        demuxer.parse()?;
+        Ok(demuxer)
+    }
+
+    /// All tracks declared in `moov`, for fragment parsing
+    pub(crate) fn tracks(&self) -> &[Mp4Track] { &self.tracks }
+
+    /// Sample table in decode order, for seek indexes
+    pub fn samples(&self) -> &[SampleInfo] { &self.samples }
+
+    fn parse(&mut self) -> DemuxerResult<()> {
+        let len = self.data.len();
+        let tops: Vec<_> = children(&self.data, 0, len).collect();
+        let Some(&(_, moov, moov_end)) = tops.iter().find(|(t, _, _)| *t == BoxType::MOOV) else {
+            return Err(DemuxerError::InvalidContainer("No moov box".into()));
+        };
+        self.parse_moov(moov, moov_end);
+
+        for (box_type, start, end) in tops {
+            if box_type == BoxType::MOOF {
+                let header_len = if read_u32(&self.data, start - 8) == 1 { 16 } else { 8 };
+                let mut samples = parse_moof(&self.data, start - header_len, end, &mut self.tracks);
+                self.samples.append(&mut samples);
+                self.init_data.extend(pssh_boxes(&self.data, start, end));
+            }
+        }
+
+        self.video_track = self.tracks.iter().find(|t| t.info.track_type == TrackType::Video).map(|t| t.info.clone());
+        self.audio_track = self.tracks.iter().find(|t| t.info.track_type == TrackType::Audio).map(|t| t.info.clone());
+
+        // Interleave tracks in decode order
+        self.samples.sort_by_key(|s| s.dts);
+        Ok(())
+    }
+
+    fn parse_moov(&mut self, start: usize, end: usize) {
+        let data = std::mem::take(&mut self.data);
+        for (box_type, payload, box_end) in children(&data, start, end) {
+            match box_type {
+                BoxType::MVHD => {
+                    let (timescale, dur) = if data[payload] == 1 {
+                        (read_u32(&data, payload + 20), read_u64(&data, payload + 24))
+                    } else {
+                        (read_u32(&data, payload + 12), read_u32(&data, payload + 16) as u64)
+                    };
+                    self.timescale = timescale;
+                    self.duration = ticks_to_duration(dur as i64, timescale);
+                }
+                BoxType::TRAK => {
+                    if let Some(track) = Self::parse_trak(&data, payload, box_end, &mut self.samples) {
+                        self.tracks.push(track);
+                    }
+                }
+                BoxType::MVEX => {
+                    for (t, trex, _) in children(&data, payload, box_end) {
+                        if t != BoxType::TREX { continue; }
+                        let id = read_u32(&data, trex + 4);
+                        if let Some(track) = self.tracks.iter_mut().find(|tr| tr.info.track_id == id) {
+                            track.default_duration = read_u32(&data, trex + 12);
+                            track.default_size = read_u32(&data, trex + 16);
+                            track.default_flags = read_u32(&data, trex + 20);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.init_data.extend(pssh_boxes(&data, start, end));
+        self.data = data;
+    }
+
+    fn parse_trak(data: &[u8], start: usize, end: usize, samples: &mut Vec<SampleInfo>) -> Option<Mp4Track> {
+        let (tkhd, _) = find_child(data, start, end, BoxType::TKHD)?;
+        let track_id = read_u32(data, if data[tkhd] == 1 { tkhd + 20 } else { tkhd + 12 });
+        let (mdia, mdia_end) = find_child(data, start, end, BoxType::MDIA)?;
+
+        let mut info = TrackInfo {
+            track_id, track_type: TrackType::Data, codec: CodecId::Unknown, duration: Duration::ZERO,
+            timescale: 1000, width: None, height: None, frame_rate: None, sample_rate: None,
+            channels: None, codec_private: Vec::new(), encryption: None,
+        };
+        if let Some((mdhd, _)) = find_child(data, mdia, mdia_end, BoxType::MDHD) {
+            let (timescale, dur) = if data[mdhd] == 1 {
+                (read_u32(data, mdhd + 20), read_u64(data, mdhd + 24))
+            } else {
+                (read_u32(data, mdhd + 12), read_u32(data, mdhd + 16) as u64)
+            };
+            info.timescale = timescale.max(1);
+            info.duration = ticks_to_duration(dur as i64, info.timescale);
+        }
+        if let Some((hdlr, _)) = find_child(data, mdia, mdia_end, BoxType::HDLR) {
+            info.track_type = match data.get(hdlr + 8..hdlr + 12) {
+                Some(b"vide") => TrackType::Video,
+                Some(b"soun") => TrackType::Audio,
+                Some(b"subt") | Some(b"text") | Some(b"sbtl") => TrackType::Subtitle,
+                _ => TrackType::Data,
+            };
+        }
+
+        // First non-empty edit shifts the media timeline
+        let mut edit_offset = 0i64;
+        if let Some((edts, edts_end)) = find_child(data, start, end, BoxType::EDTS) {
+            if let Some((elst, _)) = find_child(data, edts, edts_end, BoxType::ELST) {
+                let v1 = data[elst] == 1;
+                let count = read_u32(data, elst + 4) as usize;
+                let entry = if v1 { 20 } else { 12 };
+                for i in 0..count.min(16) {
+                    let e = elst + 8 + i * entry;
+                    let media_time = if v1 { read_u64(data, e + 8) as i64 } else { read_u32(data, e + 4) as i32 as i64 };
+                    if media_time >= 0 { edit_offset = media_time; break; }
+                }
+            }
+        }
+
+        let mut track = Mp4Track { info, edit_offset, default_duration: 0, default_size: 0, default_flags: 0, next_dts: 0 };
+        let (minf, minf_end) = find_child(data, mdia, mdia_end, BoxType::MINF)?;
+        if let Some((stbl, stbl_end)) = find_child(data, minf, minf_end, BoxType::STBL) {
+            Self::parse_stbl(data, stbl, stbl_end, &mut track, samples);
+        }
+        Some(track)
+    }
+
+    /// Sample description: codec, dimensions / audio format and decoder config
+    fn parse_sample_entry(data: &[u8], entry: usize, entry_end: usize, info: &mut TrackInfo) {
+        let mut fourcc = [data[entry + 4], data[entry + 5], data[entry + 6], data[entry + 7]];
+        let payload = entry + 8;
+        let children_start = match info.track_type {
+            TrackType::Video => {
+                info.width = Some(read_u16(data, payload + 24) as u32);
+                info.height = Some(read_u16(data, payload + 26) as u32);
+                payload + 78
+            }
+            TrackType::Audio => {
+                info.channels = Some(read_u16(data, payload + 16) as u32);
+                info.sample_rate = Some(read_u32(data, payload + 24) >> 16);
+                payload + 28 + match read_u16(data, payload + 8) { 1 => 16, 2 => 36, _ => 0 }
+            }
+            _ => payload + 8,
+        };
+
+        // Protected entries name the original format in sinf/frma, the
+        // scheme in schm and the default key in schi/tenc
+        if &fourcc == b"encv" || &fourcc == b"enca" {
+            if let Some((sinf, sinf_end)) = find_child(data, children_start, entry_end, BoxType::SINF) {
+                if let Some((frma, _)) = find_child(data, sinf, sinf_end, BoxType::FRMA) {
+                    fourcc.copy_from_slice(&data[frma..frma + 4]);
+                }
+                let scheme = find_child(data, sinf, sinf_end, BoxType::SCHM)
+                    .and_then(|(schm, _)| data.get(schm + 4..schm + 8))
+                    .map_or(*b"cenc", |s| [s[0], s[1], s[2], s[3]]);
+                let tenc = find_child(data, sinf, sinf_end, BoxType::SCHI)
+                    .and_then(|(schi, schi_end)| find_child(data, schi, schi_end, BoxType::TENC))
+                    .filter(|&(tenc, tenc_end)| tenc + 24 <= tenc_end);
+                if let Some((tenc, _)) = tenc {
+                    let mut default_key_id = [0u8; 16];
+                    default_key_id.copy_from_slice(&data[tenc + 8..tenc + 24]);
+                    info.encryption = Some(TrackEncryption { scheme, default_key_id, default_iv_size: data[tenc + 7] });
+                }
+            }
+        }
+
+        info.codec = match &fourcc {
+            b"avc1" | b"avc3" => CodecId::H264,
+            b"hvc1" | b"hev1" => CodecId::H265,
+            b"vp08" => CodecId::Vp8,
+            b"vp09" => CodecId::Vp9,
+            b"av01" => CodecId::Av1,
+            b"mp4a" => CodecId::Aac,
+            b".mp3" => CodecId::Mp3,
+            b"Opus" => CodecId::Opus,
+            b"fLaC" => CodecId::Flac,
+            b"wvtt" => CodecId::WebVtt,
+            _ => CodecId::Unknown,
+        };
+
+        for (box_type, payload, box_end) in children(data, children_start, entry_end) {
+            match box_type {
+                BoxType::AVCC | BoxType::HVCC | BoxType::AV1C => info.codec_private = data[payload..box_end].to_vec(),
+                // Full boxes: skip version and flags
+                BoxType::VPCC | BoxType::DFLA => info.codec_private = data[payload + 4..box_end].to_vec(),
+                BoxType::DOPS => info.codec_private = data[payload..box_end].to_vec(),
+                BoxType::ESDS => {
+                    if let Some((object_type, dsi)) = Self::parse_esds(&data[payload + 4..box_end]) {
+                        if object_type == 0x69 || object_type == 0x6B { info.codec = CodecId::Mp3; }
+                        info.codec_private = dsi;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Extract (objectTypeIndication, DecoderSpecificInfo) from an ES descriptor
+    fn parse_esds(es: &[u8]) -> Option<(u8, Vec<u8>)> {
+        fn descriptor(d: &[u8], pos: &mut usize) -> Option<(u8, usize)> {
+            let tag = *d.get(*pos)?;
+            *pos += 1;
+            let mut len = 0usize;
+            for _ in 0..4 {
+                let b = *d.get(*pos)?;
+                *pos += 1;
+                len = (len << 7) | (b & 0x7F) as usize;
+                if b & 0x80 == 0 { break; }
+            }
+            Some((tag, len))
+        }
+        let mut pos = 0;
+        let (tag, _) = descriptor(es, &mut pos)?;
+        if tag != 0x03 { return None; }
+        let flags = *es.get(pos + 2)?;
+        pos += 3;
+        if flags & 0x80 != 0 { pos += 2; }
+        if flags & 0x40 != 0 { pos += 1 + *es.get(pos)? as usize; }
+        if flags & 0x20 != 0 { pos += 2; }
+        let (tag, _) = descriptor(es, &mut pos)?;
+        if tag != 0x04 { return None; }
+        let object_type = *es.get(pos)?;
+        pos += 13;
+        let (tag, len) = descriptor(es, &mut pos)?;
+        let dsi = if tag == 0x05 { es.get(pos..pos + len)?.to_vec() } else { Vec::new() };
+        Some((object_type, dsi))
+    }
+
+    fn parse_stbl(data: &[u8], start: usize, end: usize, track: &mut Mp4Track, samples: &mut Vec<SampleInfo>) {
+        let mut stts = Vec::new();
+        let mut ctts = Vec::new();
+        let mut sync: Option<Vec<u32>> = None;
+        let mut stsc = Vec::new();
+        let mut sizes = Vec::new();
+        let mut chunks = Vec::new();
+
+        for (box_type, p, box_end) in children(data, start, end) {
+            let count = read_u32(data, p + 4) as usize;
+            // Entry counts may not exceed what the box can hold
+            let fits = |entry: usize| count.min(box_end.saturating_sub(p + 8) / entry);
+            match box_type {
+                BoxType::STSD => {
+                    if let Some((_, entry_payload, entry_end)) = box_header(data, p + 8, box_end) {
+                        Self::parse_sample_entry(data, entry_payload - 8, entry_end, &mut track.info);
+                    }
+                }
+                BoxType::STTS => stts = (0..fits(8)).map(|i| (read_u32(data, p + 8 + i * 8), read_u32(data, p + 12 + i * 8))).collect(),
+                BoxType::CTTS => {
+                    let v1 = data[p] == 1;
+                    ctts = (0..fits(8)).map(|i| {
+                        let raw = read_u32(data, p + 12 + i * 8);
+                        (read_u32(data, p + 8 + i * 8), if v1 { raw as i32 as i64 } else { raw as i64 })
+                    }).collect();
+                }
+                BoxType::STSS => sync = Some((0..fits(4)).map(|i| read_u32(data, p + 8 + i * 4)).collect()),
+                BoxType::STSC => stsc = (0..fits(12)).map(|i| (read_u32(data, p + 8 + i * 12), read_u32(data, p + 12 + i * 12))).collect(),
+                BoxType::STSZ => {
+                    let uniform = read_u32(data, p + 4);
+                    let count = read_u32(data, p + 8) as usize;
+                    sizes = if uniform != 0 {
+                        vec![uniform; count.min(1 << 24)]
+                    } else {
+                        (0..count.min(box_end.saturating_sub(p + 12) / 4)).map(|i| read_u32(data, p + 12 + i * 4)).collect()
+                    };
+                }
+                BoxType::STCO => chunks = (0..fits(4)).map(|i| read_u32(data, p + 8 + i * 4) as u64).collect(),
+                BoxType::CO64 => chunks = (0..fits(8)).map(|i| read_u64(data, p + 8 + i * 8)).collect(),
+                _ => {}
+            }
+        }
+        if track.info.track_type == TrackType::Video && !stts.is_empty() && stts[0].1 > 0 {
+            track.info.frame_rate = Some(track.info.timescale as f64 / stts[0].1 as f64);
+        }
+
+        let mut durations = stts.iter().flat_map(|&(n, d)| std::iter::repeat_n(d, n as usize));
+        let mut offsets = ctts.iter().flat_map(|&(n, o)| std::iter::repeat_n(o, n as usize));
+        let mut sync_iter = sync.as_ref().map(|s| s.iter().peekable());
+        let mut sample = 0usize;
+        let mut dts = 0u64;
+        for (chunk_idx, &chunk_offset) in chunks.iter().enumerate() {
+            let chunk_num = chunk_idx as u32 + 1;
+            let per_chunk = stsc.iter().rev().find(|&&(first, _)| first <= chunk_num).map(|&(_, n)| n).unwrap_or(0);
+            let mut offset = chunk_offset;
+            for _ in 0..per_chunk {
+                let Some(&size) = sizes.get(sample) else { break };
+                let number = sample as u32 + 1;
+                let is_key = match sync_iter.as_mut() {
+                    Some(it) => {
+                        while it.peek().is_some_and(|&&n| n < number) { it.next(); }
+                        it.peek().is_some_and(|&&n| n == number)
+                    }
+                    None => true,
+                };
+                let duration = durations.next().unwrap_or(0);
+                let cts = offsets.next().unwrap_or(0);
+                samples.push(SampleInfo::new(track, offset, size, dts, cts, duration, is_key));
+                offset = offset.saturating_add(size as u64);
+                dts = dts.saturating_add(duration as u64);
+                sample += 1;
+            }
+        }
+        track.next_dts = dts;
+    }
+}
+
+impl Demuxer for Mp4Demuxer {
+    fn duration(&self) -> Option<Duration> { Some(self.duration) }
+    fn video_track(&self) -> Option<&TrackInfo> { self.video_track.as_ref() }
+    fn audio_track(&self) -> Option<&TrackInfo> { self.audio_track.as_ref() }
+
+    fn read_packet(&mut self) -> DemuxerResult<Packet> {
+        if self.current_sample >= self.samples.len() { self.eof = true; return Err(DemuxerError::EndOfStream); }
+        let s = &self.samples[self.current_sample];
+        let offset = s.offset as usize;
+        let size = s.size as usize;
+        let Some(data) = self.data.get(offset..offset.saturating_add(size)) else {
+            return Err(DemuxerError::IoError("Out of bounds".into()));
+        };
+        let packet = s.packet(data.to_vec());
+        self.current_sample += 1;
+        Ok(packet)
+    }
+
+    fn seek(&mut self, position: Duration) -> DemuxerResult<()> {
+        self.current_sample = seek_index(&self.samples, self.video_track.as_ref().map(|t| t.track_id), position, |s| (s.track_id, s.pts, s.dts, s.is_key));
+        self.eof = false;
+        Ok(())
+    }
+
+    fn is_eof(&self) -> bool { self.eof }
+    fn init_data(&self) -> &[InitData] { &self.init_data }
+}
+
+/// Index to resume reading from so that `position` can be decoded: the last
+/// sync sample of the video track at or before `position` (or of any track
+/// when there is no video). `key` maps a sample to (track, pts, dts, is_key).
+pub fn seek_index<T>(items: &[T], video_track: Option<u32>, position: Duration, key: impl Fn(&T) -> (u32, Duration, Duration, bool)) -> usize {
+    let sync = items.iter().enumerate().filter(|(_, s)| {
+        let (track, _, _, is_key) = key(s);
+        is_key && video_track.is_none_or(|v| v == track)
+    });
+    let target = sync.clone().filter(|(_, s)| key(s).1 <= position).last().or_else(|| sync.clone().next());
+    match target {
+        Some((_, s)) => {
+            let dts = key(s).2;
+            items.iter().position(|s| key(s).2 >= dts).unwrap_or(0)
+        }
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{full_box, init_segment, init_segment_with, media_segment, media_segment_with, mp4_box, u32s};
+
+    #[test]
+    fn test_box_type() { assert_eq!(BoxType::FTYP.0, *b"ftyp"); }
+
+    #[test]
+    fn test_sample_tables() {
+        let tables = [
+            full_box(b"stts", 0, 0, &u32s(&[1, 3, 100])),
+            full_box(b"stss", 0, 0, &u32s(&[1, 1])),
+            full_box(b"stsc", 0, 0, &u32s(&[1, 1, 3, 1])),
+            full_box(b"stsz", 0, 0, &u32s(&[0, 3, 4, 2, 2])),
+            full_box(b"stco", 0, 0, &u32s(&[1, 0])),
+        ].concat();
+        let mut file = init_segment(&tables, false);
+        let mdat_start = file.len() as u32 + 8;
+        // Patch the chunk offset to point at the mdat payload
+        let stco = file.windows(4).position(|w| w == b"stco").unwrap() + 12;
+        file[stco..stco + 4].copy_from_slice(&mdat_start.to_be_bytes());
+        file.extend(mp4_box(b"mdat", &[1, 1, 1, 1, 2, 2, 3, 3]));
+
+        let mut d = Mp4Demuxer::new(file).unwrap();
+        let track = d.video_track().unwrap();
+        assert_eq!((track.codec, track.width, track.height), (CodecId::H264, Some(320), Some(240)));
+        assert_eq!(track.codec_private[0], 1);
+        let p = d.read_packet().unwrap();
+        assert_eq!((p.data, p.is_key), (vec![1, 1, 1, 1], true));
+        let p = d.read_packet().unwrap();
+        assert_eq!((p.data, p.pts, p.is_key), (vec![2, 2], Duration::from_millis(100), false));
+
+        d.seek(Duration::from_millis(250)).unwrap();
+        assert_eq!(d.read_packet().unwrap().pts, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_fragmented_file() {
+        let file = [init_segment(&[], true), media_segment(0, &[3, 2]), media_segment(200, &[1])].concat();
+        let mut d = Mp4Demuxer::new(file).unwrap();
+        let p = d.read_packet().unwrap();
+        assert_eq!((p.data, p.is_key), (vec![0, 0, 0], true));
+        let p = d.read_packet().unwrap();
+        assert_eq!((p.data, p.pts, p.is_key), (vec![1, 1], Duration::from_millis(100), false));
+        let p = d.read_packet().unwrap();
+        assert_eq!((p.pts, p.duration), (Duration::from_millis(200), Duration::from_millis(100)));
+
+        // A trun without per-sample fields cannot claim more samples than bytes
+        let tfhd = full_box(b"tfhd", 0, 0x02_0000 | 0x10, &u32s(&[1, 1]));
+        let trun = full_box(b"trun", 0, 0, &u32s(&[u32::MAX]));
+        let moof = mp4_box(b"moof", &mp4_box(b"traf", &[tfhd, trun].concat()));
+        let mut d = Mp4Demuxer::new([init_segment(&[], true), moof].concat()).unwrap();
+        assert!(d.read_packet().is_err());
+    }
+
+    #[test]
+    fn test_cenc_metadata() {
+        let kid = [0xABu8; 16];
+        let mut tenc = vec![0, 0, 1, 8];
+        tenc.extend_from_slice(&kid);
+        let sinf = mp4_box(b"sinf", &[
+            mp4_box(b"frma", b"avc1"),
+            full_box(b"schm", 0, 0, &[b"cenc".to_vec(), u32s(&[0x10000])].concat()),
+            mp4_box(b"schi", &full_box(b"tenc", 0, 0, &tenc)),
+        ].concat());
+        let pssh = full_box(b"pssh", 0, 0, &[vec![0x10; 16], u32s(&[0])].concat());
+        // Sample 1: whole-sample IV only; sample 2: two subsamples
+        let mut senc = u32s(&[2]);
+        senc.extend_from_slice(&[1; 8]);
+        senc.extend([0, 0]);
+        senc.extend_from_slice(&[2; 8]);
+        senc.extend([0, 2, 0, 1]);
+        senc.extend(u32s(&[0]));
+        senc.extend([0, 0]);
+        senc.extend(u32s(&[2]));
+        let file = [
+            init_segment_with(b"encv", &sinf, &pssh, &[], true),
+            media_segment_with(0, &[3, 3], &full_box(b"senc", 0, 0x02, &senc)),
+        ].concat();
+
+        let mut d = Mp4Demuxer::new(file).unwrap();
+        let track = d.video_track().unwrap();
+        assert_eq!(track.codec, CodecId::H264);
+        assert_eq!(track.encryption.as_ref().map(|e| (e.scheme, e.default_key_id, e.default_iv_size)), Some((*b"cenc", kid, 8)));
+        assert_eq!(d.init_data(), &[InitData { init_data_type: "cenc".into(), data: pssh }]);
+
+        let first = d.read_packet().unwrap().encryption.unwrap();
+        assert_eq!((first.key_id, first.iv, first.subsamples.len()), (kid, vec![1; 8], 0));
+        let second = d.read_packet().unwrap().encryption.unwrap();
+        assert_eq!(second.subsamples, vec![
+            Subsample { clear_bytes: 1, encrypted_bytes: 0 },
+            Subsample { clear_bytes: 0, encrypted_bytes: 2 },
+        ]);
+    }
+}
@@ -3,6 +3,7 @@
 //! MPEG Transport Stream parser for HLS segments.
 
 use super::{Demuxer, DemuxerResult, DemuxerError, TrackInfo, TrackType, CodecId, Packet};
+use std::ops::Range;
 use std::time::Duration;
 
 const TS_PACKET_SIZE: usize = 188;
@@ -26,12 +27,13 @@ pub struct TsDemuxer {
 
 /// TS Packet header
 #[derive(Debug)]
-struct TsPacket<'a> {
+struct TsPacket {
     pid: u16,
     payload_unit_start: bool,
     adaptation_field_control: u8,
     continuity_counter: u8,
-    payload: &'a [u8],
+    /// Payload bytes within the stream data
+    payload: Range<usize>,
 }
 
 impl TsDemuxer {
@@ -62,9 +64,9 @@ impl TsDemuxer {
         }
         
         let payload = if adaptation_field_control & 0x01 != 0 && payload_start < offset + TS_PACKET_SIZE {
-            &self.data[payload_start..offset + TS_PACKET_SIZE]
+            payload_start..offset + TS_PACKET_SIZE
         } else {
-            &[]
+            0..0
         };
         
         Ok(TsPacket { pid, payload_unit_start, adaptation_field_control, continuity_counter, payload })
@@ -75,11 +77,12 @@ impl TsDemuxer {
         while pos + TS_PACKET_SIZE <= self.data.len() {
             if self.data[pos] == SYNC_BYTE {
                 if let Ok(pkt) = self.parse_packet(pos) {
+                    let payload = self.data[pkt.payload].to_vec();
                     // PAT is PID 0
-                    if pkt.pid == 0 && !pkt.payload.is_empty() { self.parse_pat(pkt.payload)?; }
+                    if pkt.pid == 0 && !payload.is_empty() { self.parse_pat(&payload)?; }
                     // PMT
                     if pkt.pid > 0 && pkt.pid < 0x1FFF && self.video_pid.is_none() {
-                        self.parse_pmt(pkt.payload);
+                        self.parse_pmt(&payload);
                     }
                 }
                 pos += TS_PACKET_SIZE;
@@ -127,7 +130,7 @@ impl TsDemuxer {
                         track_id: pid as u32, track_type: TrackType::Video, codec: CodecId::H264,
                         duration: Duration::ZERO, timescale: 90000,
                         width: Some(1920), height: Some(1080), frame_rate: Some(30.0),
-                        sample_rate: None, channels: None, codec_private: Vec::new(),
+                        sample_rate: None, channels: None, codec_private: Vec::new(), encryption: None,
                     });
                 }
                 0x24 => { // H.265
@@ -136,7 +139,7 @@ impl TsDemuxer {
                         track_id: pid as u32, track_type: TrackType::Video, codec: CodecId::H265,
                         duration: Duration::ZERO, timescale: 90000,
                         width: Some(1920), height: Some(1080), frame_rate: Some(30.0),
-                        sample_rate: None, channels: None, codec_private: Vec::new(),
+                        sample_rate: None, channels: None, codec_private: Vec::new(), encryption: None,
                     });
                 }
                 0x0F | 0x11 => { // AAC
@@ -145,7 +148,7 @@ impl TsDemuxer {
                         track_id: pid as u32, track_type: TrackType::Audio, codec: CodecId::Aac,
                         duration: Duration::ZERO, timescale: 90000,
                         width: None, height: None, frame_rate: None,
-                        sample_rate: Some(48000), channels: Some(2), codec_private: Vec::new(),
+                        sample_rate: Some(48000), channels: Some(2), codec_private: Vec::new(), encryption: None,
                     });
                 }
                 _ => {}
@@ -183,39 +186,40 @@ impl Demuxer for TsDemuxer {
         while self.pos + TS_PACKET_SIZE <= self.data.len() {
             let pkt = self.parse_packet(self.pos)?;
             self.pos += TS_PACKET_SIZE;
+            let payload = &self.data[pkt.payload];
             
-            if Some(pkt.pid) == self.video_pid && !pkt.payload.is_empty() {
+            if Some(pkt.pid) == self.video_pid && !payload.is_empty() {
                 if pkt.payload_unit_start && !self.video_buffer.is_empty() {
                     let data = std::mem::take(&mut self.video_buffer);
                     return Ok(Packet {
                         track_id: pkt.pid as u32, pts: self.video_pts, dts: self.video_pts,
                         duration: Duration::from_millis(33), is_key: data.get(4).map(|b| b & 0x1F == 5).unwrap_or(false),
-                        data,
+                        data, encryption: None,
                     });
                 }
                 if pkt.payload_unit_start {
-                    let (pts, skip) = self.parse_pes_header(pkt.payload);
+                    let (pts, skip) = self.parse_pes_header(payload);
                     self.video_pts = pts;
-                    self.video_buffer.extend_from_slice(&pkt.payload[skip.min(pkt.payload.len())..]);
+                    self.video_buffer.extend_from_slice(&payload[skip.min(payload.len())..]);
                 } else {
-                    self.video_buffer.extend_from_slice(pkt.payload);
+                    self.video_buffer.extend_from_slice(payload);
                 }
             }
             
-            if Some(pkt.pid) == self.audio_pid && !pkt.payload.is_empty() {
+            if Some(pkt.pid) == self.audio_pid && !payload.is_empty() {
                 if pkt.payload_unit_start && !self.audio_buffer.is_empty() {
                     let data = std::mem::take(&mut self.audio_buffer);
                     return Ok(Packet {
                         track_id: pkt.pid as u32, pts: self.audio_pts, dts: self.audio_pts,
-                        duration: Duration::from_millis(21), is_key: true, data,
+                        duration: Duration::from_millis(21), is_key: true, data, encryption: None,
                     });
                 }
                 if pkt.payload_unit_start {
-                    let (pts, skip) = self.parse_pes_header(pkt.payload);
+                    let (pts, skip) = self.parse_pes_header(payload);
                     self.audio_pts = pts;
-                    self.audio_buffer.extend_from_slice(&pkt.payload[skip.min(pkt.payload.len())..]);
+                    self.audio_buffer.extend_from_slice(&payload[skip.min(payload.len())..]);
                 } else {
-                    self.audio_buffer.extend_from_slice(pkt.payload);
+                    self.audio_buffer.extend_from_slice(payload);
                 }
             }
         }
@@ -224,7 +228,7 @@ impl Demuxer for TsDemuxer {
         if !self.video_buffer.is_empty() {
             let data = std::mem::take(&mut self.video_buffer);
             self.eof = true;
-            return Ok(Packet { track_id: self.video_pid.unwrap_or(0) as u32, pts: self.video_pts, dts: self.video_pts, duration: Duration::from_millis(33), is_key: false, data });
+            return Ok(Packet { track_id: self.video_pid.unwrap_or(0) as u32, pts: self.video_pts, dts: self.video_pts, duration: Duration::from_millis(33), is_key: false, data, encryption: None });
         }
         
         self.eof = true;
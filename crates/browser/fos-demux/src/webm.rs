@@ -0,0 +1,676 @@
+//! WebM Parser
+//!
+//! EBML-based WebM/Matroska container parser. Data may be supplied all at
+//! once or appended incrementally (MSE); the Segment and Cluster masters are
+//! entered rather than skipped, so unknown-size (live) streams work.
+//!
+//! [`WebMMuxer`] writes the same kind of live stream, for MediaRecorder.
+
+use super::{Demuxer, DemuxerResult, DemuxerError, TrackInfo, TrackType, CodecId, Packet};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// EBML Element IDs
+const EBML_ID: u32 = 0x1A45DFA3;
+const SEGMENT_ID: u32 = 0x18538067;
+const INFO_ID: u32 = 0x1549A966;
+const TRACKS_ID: u32 = 0x1654AE6B;
+const TRACK_ENTRY_ID: u32 = 0xAE;
+const CLUSTER_ID: u32 = 0x1F43B675;
+const TIMECODE_ID: u32 = 0xE7;
+const SIMPLE_BLOCK_ID: u32 = 0xA3;
+const BLOCK_GROUP_ID: u32 = 0xA0;
+const BLOCK_ID: u32 = 0xA1;
+const BLOCK_DURATION_ID: u32 = 0x9B;
+const REFERENCE_BLOCK_ID: u32 = 0xFB;
+const TIMECODE_SCALE_ID: u32 = 0x2AD7B1;
+const DURATION_ID: u32 = 0x4489;
+const TRACK_NUMBER_ID: u32 = 0xD7;
+const TRACK_TYPE_ID: u32 = 0x83;
+const DEFAULT_DURATION_ID: u32 = 0x23E383;
+const CODEC_ID: u32 = 0x86;
+const VIDEO_ID: u32 = 0xE0;
+const AUDIO_ID: u32 = 0xE1;
+const PIXEL_WIDTH_ID: u32 = 0xB0;
+const PIXEL_HEIGHT_ID: u32 = 0xBA;
+const SAMPLING_FREQ_ID: u32 = 0xB5;
+const CHANNELS_ID: u32 = 0x9F;
+const CODEC_PRIVATE_ID: u32 = 0x63A2;
+const EBML_VERSION_ID: u32 = 0x4286;
+const EBML_READ_VERSION_ID: u32 = 0x42F7;
+const EBML_MAX_ID_LENGTH_ID: u32 = 0x42F2;
+const EBML_MAX_SIZE_LENGTH_ID: u32 = 0x42F3;
+const DOC_TYPE_ID: u32 = 0x4282;
+const DOC_TYPE_VERSION_ID: u32 = 0x4287;
+const DOC_TYPE_READ_VERSION_ID: u32 = 0x4285;
+const MUXING_APP_ID: u32 = 0x4D80;
+const WRITING_APP_ID: u32 = 0x5741;
+const TRACK_UID_ID: u32 = 0x73C5;
+
+/// Size value meaning "unknown" (all data bits set)
+const UNKNOWN_SIZE: u64 = u64::MAX;
+
+/// Read an element ID (marker bits kept). None if more data is needed.
+fn read_id(data: &[u8], pos: usize) -> DemuxerResult<Option<(u32, usize)>> {
+    let Some(&first) = data.get(pos) else { return Ok(None) };
+    let len = first.leading_zeros() as usize + 1;
+    if len > 4 { return Err(DemuxerError::InvalidContainer("Invalid EBML element ID".into())); }
+    let Some(bytes) = data.get(pos..pos + len) else { return Ok(None) };
+    Ok(Some((bytes.iter().fold(0u32, |id, &b| (id << 8) | b as u32), len)))
+}
+
+/// Read a variable-size integer (marker bit removed)
+fn read_vint(data: &[u8], pos: usize) -> DemuxerResult<Option<(u64, usize)>> {
+    let Some(&first) = data.get(pos) else { return Ok(None) };
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 { return Err(DemuxerError::InvalidContainer("Invalid EBML size".into())); }
+    let Some(bytes) = data.get(pos..pos + len) else { return Ok(None) };
+    let mask = if len == 8 { 0 } else { 0xFFu8 >> len };
+    let value = bytes[1..].iter().fold((first & mask) as u64, |v, &b| (v << 8) | b as u64);
+    let all_ones = (1u64 << (7 * len)) - 1;
+    Ok(Some((if value == all_ones { UNKNOWN_SIZE } else { value }, len)))
+}
+
+/// Element header at `pos`: (id, size, header length)
+fn element_header(data: &[u8], pos: usize) -> DemuxerResult<Option<(u32, u64, usize)>> {
+    let Some((id, id_len)) = read_id(data, pos)? else { return Ok(None) };
+    let Some((size, size_len)) = read_vint(data, pos + id_len)? else { return Ok(None) };
+    Ok(Some((id, size, id_len + size_len)))
+}
+
+/// Iterate the complete children of a master element body
+fn children(data: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        let (id, size, header) = element_header(data, pos).ok()??;
+        let start = pos + header;
+        let end = start.checked_add(usize::try_from(size).ok()?)?;
+        let body = data.get(start..end)?;
+        pos = end;
+        Some((id, body))
+    })
+}
+
+fn read_uint(data: &[u8]) -> u64 {
+    data.iter().take(8).fold(0u64, |v, &b| (v << 8) | b as u64)
+}
+
+fn read_float(data: &[u8]) -> f64 {
+    match data.len() {
+        4 => f32::from_bits(read_uint(data) as u32) as f64,
+        8 => f64::from_bits(read_uint(data)),
+        _ => 0.0,
+    }
+}
+
+/// Map a Matroska CodecID string
+fn codec_for(codec: &str) -> CodecId {
+    match codec {
+        "V_VP8" => CodecId::Vp8,
+        "V_VP9" => CodecId::Vp9,
+        "V_AV1" => CodecId::Av1,
+        "V_MPEG4/ISO/AVC" => CodecId::H264,
+        "V_MPEGH/ISO/HEVC" => CodecId::H265,
+        "A_OPUS" => CodecId::Opus,
+        "A_VORBIS" => CodecId::Vorbis,
+        "A_FLAC" => CodecId::Flac,
+        "A_MPEG/L3" => CodecId::Mp3,
+        "A_PCM/INT/LIT" => CodecId::Pcm,
+        "S_TEXT/WEBVTT" | "D_WEBVTT/SUBTITLES" => CodecId::WebVtt,
+        "S_TEXT/UTF8" => CodecId::Subrip,
+        c if c.starts_with("A_AAC") => CodecId::Aac,
+        _ => CodecId::Unknown,
+    }
+}
+
+/// Matroska CodecID of a codec WebM can carry
+fn codec_name(codec: CodecId) -> Option<&'static str> {
+    match codec {
+        CodecId::Vp8 => Some("V_VP8"),
+        CodecId::Vp9 => Some("V_VP9"),
+        CodecId::Av1 => Some("V_AV1"),
+        CodecId::Opus => Some("A_OPUS"),
+        CodecId::Vorbis => Some("A_VORBIS"),
+        _ => None,
+    }
+}
+
+/// Element ID bytes (marker bits are part of the ID)
+fn write_id(out: &mut Vec<u8>, id: u32) {
+    let bytes = id.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count();
+    out.extend_from_slice(&bytes[skip..]);
+}
+
+/// Shortest variable-size integer for `size`
+fn write_vint(out: &mut Vec<u8>, size: u64) {
+    // All ones is reserved for "unknown"
+    let len = (1..8).find(|&len| size < (1u64 << (7 * len)) - 1).unwrap_or(8);
+    let marked = size | (1u64 << (7 * len));
+    out.extend_from_slice(&marked.to_be_bytes()[8 - len..]);
+}
+
+fn write_element(out: &mut Vec<u8>, id: u32, body: &[u8]) {
+    write_id(out, id);
+    write_vint(out, body.len() as u64);
+    out.extend_from_slice(body);
+}
+
+fn write_uint(out: &mut Vec<u8>, id: u32, value: u64) {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count().min(7);
+    write_element(out, id, &bytes[skip..]);
+}
+
+/// Master element of unknown size, closed by the next element of its level
+fn write_unknown_size(out: &mut Vec<u8>, id: u32) {
+    write_id(out, id);
+    out.extend_from_slice(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+/// Live WebM writer. The Segment and its Clusters have unknown sizes, so
+/// output can be handed out as soon as it is written.
+#[derive(Debug)]
+pub struct WebMMuxer {
+    tracks: Vec<TrackInfo>,
+    /// Timecode of the open Cluster, in milliseconds
+    cluster_timecode: Option<u64>,
+}
+
+impl WebMMuxer {
+    /// Muxer for tracks of WebM codecs; `track_id` is the track number
+    pub fn new(tracks: Vec<TrackInfo>) -> DemuxerResult<Self> {
+        if let Some(track) = tracks.iter().find(|t| codec_name(t.codec).is_none()) {
+            return Err(DemuxerError::Unsupported(format!("{:?} in WebM", track.codec)));
+        }
+        Ok(Self { tracks, cluster_timecode: None })
+    }
+
+    /// EBML header, the start of the Segment, Info and Tracks
+    pub fn header(&self) -> Vec<u8> {
+        let mut ebml = Vec::new();
+        write_uint(&mut ebml, EBML_VERSION_ID, 1);
+        write_uint(&mut ebml, EBML_READ_VERSION_ID, 1);
+        write_uint(&mut ebml, EBML_MAX_ID_LENGTH_ID, 4);
+        write_uint(&mut ebml, EBML_MAX_SIZE_LENGTH_ID, 8);
+        write_element(&mut ebml, DOC_TYPE_ID, b"webm");
+        write_uint(&mut ebml, DOC_TYPE_VERSION_ID, 4);
+        write_uint(&mut ebml, DOC_TYPE_READ_VERSION_ID, 2);
+
+        let mut info = Vec::new();
+        write_uint(&mut info, TIMECODE_SCALE_ID, 1_000_000);
+        write_element(&mut info, MUXING_APP_ID, b"fOS");
+        write_element(&mut info, WRITING_APP_ID, b"fOS");
+
+        let mut tracks = Vec::new();
+        for track in &self.tracks {
+            let mut entry = Vec::new();
+            write_uint(&mut entry, TRACK_NUMBER_ID, track.track_id as u64);
+            write_uint(&mut entry, TRACK_UID_ID, track.track_id as u64);
+            let track_type = if track.track_type == TrackType::Video { 1 } else { 2 };
+            write_uint(&mut entry, TRACK_TYPE_ID, track_type);
+            write_element(&mut entry, CODEC_ID, codec_name(track.codec).unwrap_or_default().as_bytes());
+            if !track.codec_private.is_empty() {
+                write_element(&mut entry, CODEC_PRIVATE_ID, &track.codec_private);
+            }
+            if track.track_type == TrackType::Video {
+                let mut video = Vec::new();
+                write_uint(&mut video, PIXEL_WIDTH_ID, track.width.unwrap_or(0) as u64);
+                write_uint(&mut video, PIXEL_HEIGHT_ID, track.height.unwrap_or(0) as u64);
+                write_element(&mut entry, VIDEO_ID, &video);
+            } else {
+                let mut audio = Vec::new();
+                let rate = track.sample_rate.unwrap_or(48000) as f64;
+                write_element(&mut audio, SAMPLING_FREQ_ID, &rate.to_bits().to_be_bytes());
+                write_uint(&mut audio, CHANNELS_ID, track.channels.unwrap_or(1) as u64);
+                write_element(&mut entry, AUDIO_ID, &audio);
+            }
+            write_element(&mut tracks, TRACK_ENTRY_ID, &entry);
+        }
+
+        let mut out = Vec::new();
+        write_element(&mut out, EBML_ID, &ebml);
+        write_unknown_size(&mut out, SEGMENT_ID);
+        write_element(&mut out, INFO_ID, &info);
+        write_element(&mut out, TRACKS_ID, &tracks);
+        out
+    }
+
+    /// SimpleBlock at `timestamp` milliseconds. A Cluster is opened at
+    /// every video key frame and whenever the block would be out of range
+    /// of the open one.
+    pub fn write_block(&mut self, track: u32, timestamp: u64, key: bool, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let video = self.tracks.iter().any(|t| t.track_id == track && t.track_type == TrackType::Video);
+        let relative = self.cluster_timecode.map(|start| timestamp as i64 - start as i64);
+        let in_range = relative.is_some_and(|r| (i16::MIN as i64..=i16::MAX as i64).contains(&r));
+        if !in_range || (video && key) {
+            write_unknown_size(&mut out, CLUSTER_ID);
+            write_uint(&mut out, TIMECODE_ID, timestamp);
+            self.cluster_timecode = Some(timestamp);
+        }
+        let relative = (timestamp as i64 - self.cluster_timecode.unwrap_or(timestamp) as i64) as i16;
+
+        let mut block = Vec::with_capacity(data.len() + 4);
+        write_vint(&mut block, track as u64);
+        block.extend_from_slice(&relative.to_be_bytes());
+        block.push(if key { 0x80 } else { 0 });
+        block.extend_from_slice(data);
+        write_element(&mut out, SIMPLE_BLOCK_ID, &block);
+        out
+    }
+}
+
+/// WebM Demuxer
+#[derive(Debug)]
+pub struct WebMDemuxer {
+    /// Unconsumed input
+    buf: Vec<u8>,
+    /// Bytes of an uninteresting element still to be discarded
+    skip: usize,
+    header_seen: bool,
+    timecode_scale: u64,
+    duration: Duration,
+    tracks: Vec<TrackInfo>,
+    video_track: Option<TrackInfo>,
+    audio_track: Option<TrackInfo>,
+    /// DefaultDuration per track number, in nanoseconds
+    default_durations: HashMap<u32, u64>,
+    cluster_timecode: u64,
+    packets: Vec<Packet>,
+    pos: usize,
+    eof: bool,
+}
+
+impl WebMDemuxer {
+    pub fn new(data: Vec<u8>) -> DemuxerResult<Self> {
+        let mut demuxer = Self::streaming();
+        let mut packets = demuxer.append(&data)?;
+        if demuxer.tracks.is_empty() { return Err(DemuxerError::InvalidContainer("No tracks".into())); }
+        demuxer.packets.append(&mut packets);
+        Ok(demuxer)
+    }
+
+    /// Demuxer that is fed through `append`, for MSE
+    pub fn streaming() -> Self {
+        Self {
+            buf: Vec::new(), skip: 0, header_seen: false, timecode_scale: 1_000_000, duration: Duration::ZERO,
+            tracks: Vec::new(), video_track: None, audio_track: None, default_durations: HashMap::new(),
+            cluster_timecode: 0, packets: Vec::new(), pos: 0, eof: false,
+        }
+    }
+
+    /// Whether the Tracks element (the initialization segment) has been parsed
+    pub fn has_init_segment(&self) -> bool { !self.tracks.is_empty() }
+
+    /// All tracks, including subtitle tracks
+    pub fn tracks(&self) -> &[TrackInfo] { &self.tracks }
+
+    /// Feed raw bytes, returning the blocks completed by them
+    pub fn append(&mut self, data: &[u8]) -> DemuxerResult<Vec<Packet>> {
+        self.buf.extend_from_slice(data);
+        let buf = std::mem::take(&mut self.buf);
+        let mut packets = Vec::new();
+        let result = self.parse_elements(&buf, &mut packets);
+        self.buf = buf;
+        let consumed = result?;
+        self.buf.drain(..consumed);
+        Ok(packets)
+    }
+
+    /// Drop partially received data, e.g. on `SourceBuffer.abort()`
+    pub fn reset_parser(&mut self) { self.buf.clear(); self.skip = 0; }
+
+    fn parse_elements(&mut self, buf: &[u8], packets: &mut Vec<Packet>) -> DemuxerResult<usize> {
+        let mut pos = 0;
+        loop {
+            if self.skip > 0 {
+                let n = self.skip.min(buf.len() - pos);
+                pos += n;
+                self.skip -= n;
+                if self.skip > 0 { return Ok(pos); }
+            }
+            let Some((id, size, header)) = element_header(buf, pos)? else { return Ok(pos) };
+            if !self.header_seen && id != EBML_ID { return Err(DemuxerError::InvalidContainer("Not EBML".into())); }
+
+            // Masters whose children are parsed as a flat stream
+            if id == SEGMENT_ID || id == CLUSTER_ID {
+                if id == CLUSTER_ID { self.cluster_timecode = 0; }
+                pos += header;
+                continue;
+            }
+            if size == UNKNOWN_SIZE { return Err(DemuxerError::Unsupported("Unknown-size element".into())); }
+            let size = usize::try_from(size).map_err(|_| DemuxerError::InvalidContainer("Element too large".into()))?;
+            let body_start = pos + header;
+
+            match id {
+                EBML_ID | INFO_ID | TRACKS_ID | TIMECODE_ID | SIMPLE_BLOCK_ID | BLOCK_GROUP_ID => {
+                    let Some(body) = body_start.checked_add(size).and_then(|end| buf.get(body_start..end)) else { return Ok(pos) };
+                    match id {
+                        EBML_ID => self.header_seen = true,
+                        INFO_ID => self.parse_info(body),
+                        TRACKS_ID => self.parse_tracks(body),
+                        TIMECODE_ID => self.cluster_timecode = read_uint(body),
+                        SIMPLE_BLOCK_ID => self.parse_block(body, None, None, packets)?,
+                        _ => {
+                            let mut block = None;
+                            let mut referenced = false;
+                            let mut duration = None;
+                            for (child, data) in children(body) {
+                                match child {
+                                    BLOCK_ID => block = Some(data),
+                                    REFERENCE_BLOCK_ID => referenced = true,
+                                    BLOCK_DURATION_ID => duration = Some(read_uint(data)),
+                                    _ => {}
+                                }
+                            }
+                            if let Some(block) = block {
+                                self.parse_block(block, Some(!referenced), duration, packets)?;
+                            }
+                        }
+                    }
+                    pos = body_start + size;
+                }
+                // SeekHead, Cues, Tags, Void, ... are not needed for playback
+                _ => {
+                    pos = body_start;
+                    self.skip = size;
+                }
+            }
+        }
+    }
+
+    fn parse_info(&mut self, body: &[u8]) {
+        let mut duration = None;
+        for (id, data) in children(body) {
+            match id {
+                TIMECODE_SCALE_ID => self.timecode_scale = read_uint(data).max(1),
+                DURATION_ID => duration = Some(read_float(data)),
+                _ => {}
+            }
+        }
+        if let Some(d) = duration.filter(|d| d.is_finite() && *d > 0.0) {
+            self.duration = Duration::from_nanos((d * self.timecode_scale as f64) as u64);
+        }
+    }
+
+    fn parse_tracks(&mut self, body: &[u8]) {
+        // A new initialization segment replaces the track list
+        self.tracks.clear();
+        self.video_track = None;
+        self.audio_track = None;
+        self.default_durations.clear();
+        for (id, data) in children(body) {
+            if id == TRACK_ENTRY_ID { self.parse_track_entry(data); }
+        }
+    }
+
+    fn parse_track_entry(&mut self, body: &[u8]) {
+        let mut track = TrackInfo {
+            track_id: 0, track_type: TrackType::Data, codec: CodecId::Unknown, duration: self.duration,
+            timescale: 1_000_000_000, width: None, height: None, frame_rate: None, sample_rate: None,
+            channels: None, codec_private: Vec::new(), encryption: None,
+        };
+        let mut default_duration = 0;
+
+        for (id, data) in children(body) {
+            match id {
+                TRACK_NUMBER_ID => track.track_id = read_uint(data) as u32,
+                TRACK_TYPE_ID => {
+                    track.track_type = match read_uint(data) { 1 => TrackType::Video, 2 => TrackType::Audio, 17 => TrackType::Subtitle, _ => TrackType::Data };
+                }
+                CODEC_ID => track.codec = codec_for(String::from_utf8_lossy(data).trim_end_matches('\0')),
+                DEFAULT_DURATION_ID => default_duration = read_uint(data),
+                VIDEO_ID => {
+                    for (vid, v) in children(data) {
+                        match vid {
+                            PIXEL_WIDTH_ID => track.width = Some(read_uint(v) as u32),
+                            PIXEL_HEIGHT_ID => track.height = Some(read_uint(v) as u32),
+                            _ => {}
+                        }
+                    }
+                }
+                AUDIO_ID => {
+                    for (aid, a) in children(data) {
+                        match aid {
+                            SAMPLING_FREQ_ID => track.sample_rate = Some(read_float(a) as u32),
+                            CHANNELS_ID => track.channels = Some(read_uint(a) as u32),
+                            _ => {}
+                        }
+                    }
+                }
+                CODEC_PRIVATE_ID => track.codec_private = data.to_vec(),
+                _ => {}
+            }
+        }
+        if track.track_type == TrackType::Audio && track.channels.is_none() { track.channels = Some(1); }
+        if default_duration > 0 {
+            self.default_durations.insert(track.track_id, default_duration);
+            if track.track_type == TrackType::Video { track.frame_rate = Some(1e9 / default_duration as f64); }
+        }
+
+        if track.track_type == TrackType::Video && self.video_track.is_none() { self.video_track = Some(track.clone()); }
+        else if track.track_type == TrackType::Audio && self.audio_track.is_none() { self.audio_track = Some(track.clone()); }
+        self.tracks.push(track);
+    }
+
+    /// Parse a (Simple)Block. `key` is None for SimpleBlocks, which carry
+    /// their own keyframe flag; `duration` is a BlockDuration in timecode units.
+    fn parse_block(&self, body: &[u8], key: Option<bool>, duration: Option<u64>, packets: &mut Vec<Packet>) -> DemuxerResult<()> {
+        let invalid = || DemuxerError::InvalidContainer("Malformed block".into());
+        let (track, len) = read_vint(body, 0)?.ok_or_else(invalid)?;
+        let track = track as u32;
+        if !self.tracks.iter().any(|t| t.track_id == track) { return Ok(()); }
+        let header = body.get(len..len + 3).ok_or_else(invalid)?;
+        let relative = i16::from_be_bytes([header[0], header[1]]) as i64;
+        let flags = header[2];
+        let is_key = key.unwrap_or(flags & 0x80 != 0);
+        let mut pos = len + 3;
+
+        // Frame sizes for laced blocks
+        let sizes = match (flags >> 1) & 3 {
+            0 => vec![body.len() - pos],
+            lacing => {
+                let count = *body.get(pos).ok_or_else(invalid)? as usize + 1;
+                pos += 1;
+                let mut sizes = Vec::with_capacity(count);
+                match lacing {
+                    1 => {
+                        for _ in 1..count {
+                            let mut size = 0;
+                            loop {
+                                let b = *body.get(pos).ok_or_else(invalid)?;
+                                pos += 1;
+                                size += b as usize;
+                                if b != 255 { break; }
+                            }
+                            sizes.push(size);
+                        }
+                    }
+                    3 => {
+                        let (first, n) = read_vint(body, pos)?.ok_or_else(invalid)?;
+                        pos += n;
+                        if first > body.len() as u64 { return Err(invalid()); }
+                        let mut size = first as i64;
+                        sizes.push(first as usize);
+                        for _ in 2..count {
+                            let (raw, n) = read_vint(body, pos)?.ok_or_else(invalid)?;
+                            pos += n;
+                            size += raw as i64 - ((1i64 << (7 * n - 1)) - 1);
+                            if size < 0 || size > body.len() as i64 { return Err(invalid()); }
+                            sizes.push(size as usize);
+                        }
+                    }
+                    _ => {
+                        let remaining = body.len().checked_sub(pos).ok_or_else(invalid)?;
+                        sizes = vec![remaining / count; count - 1];
+                    }
+                }
+                let end = sizes.iter().try_fold(pos, |end, &size| end.checked_add(size)).ok_or_else(invalid)?;
+                let last = body.len().checked_sub(end).ok_or_else(invalid)?;
+                sizes.push(last);
+                sizes
+            }
+        };
+
+        let scale = self.timecode_scale;
+        let timestamp = (self.cluster_timecode as i64).saturating_add(relative).max(0) as u64;
+        let timestamp = timestamp.saturating_mul(scale);
+        let default = self.default_durations.get(&track).copied();
+        let frame_duration = match (duration, default) {
+            (Some(d), _) => d.saturating_mul(scale) / sizes.len() as u64,
+            (None, Some(d)) => d,
+            (None, None) => 0,
+        };
+        for (i, size) in sizes.into_iter().enumerate() {
+            let pts = Duration::from_nanos(timestamp.saturating_add((i as u64).saturating_mul(frame_duration)));
+            packets.push(Packet {
+                track_id: track, pts, dts: pts, duration: Duration::from_nanos(frame_duration),
+                is_key,
+                data: body[pos..pos + size].to_vec(),
+                encryption: None,
+            });
+            pos += size;
+        }
+        Ok(())
+    }
+}
+
+impl Demuxer for WebMDemuxer {
+    fn duration(&self) -> Option<Duration> { Some(self.duration) }
+    fn video_track(&self) -> Option<&TrackInfo> { self.video_track.as_ref() }
+    fn audio_track(&self) -> Option<&TrackInfo> { self.audio_track.as_ref() }
+
+    fn read_packet(&mut self) -> DemuxerResult<Packet> {
+        match self.packets.get(self.pos) {
+            Some(packet) => { self.pos += 1; Ok(packet.clone()) }
+            None => { self.eof = true; Err(DemuxerError::EndOfStream) }
+        }
+    }
+
+    fn seek(&mut self, position: Duration) -> DemuxerResult<()> {
+        let video = self.video_track.as_ref().map(|t| t.track_id);
+        self.pos = super::mp4::seek_index(&self.packets, video, position, |p| (p.track_id, p.pts, p.dts, p.is_key));
+        self.eof = false;
+        Ok(())
+    }
+
+    fn is_eof(&self) -> bool { self.eof }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(id: u32, body: &[u8]) -> Vec<u8> {
+        let id_bytes = id.to_be_bytes();
+        let skip = id_bytes.iter().take_while(|&&b| b == 0).count();
+        let mut out = id_bytes[skip..].to_vec();
+        out.extend_from_slice(&(0x0100_0000_0000_0000u64 | body.len() as u64).to_be_bytes());
+        out.extend_from_slice(body);
+        out
+    }
+
+    /// EBML header, Info and a single VP9 320x240 video track (number 1)
+    fn header() -> Vec<u8> {
+        let video = element(VIDEO_ID, &[element(PIXEL_WIDTH_ID, &[1, 64]), element(PIXEL_HEIGHT_ID, &[240])].concat());
+        let entry = element(TRACK_ENTRY_ID, &[
+            element(TRACK_NUMBER_ID, &[1]), element(TRACK_TYPE_ID, &[1]), element(CODEC_ID, b"V_VP9"),
+            element(DEFAULT_DURATION_ID, &33_333_333u32.to_be_bytes()), video,
+        ].concat());
+        let info = element(INFO_ID, &[element(TIMECODE_SCALE_ID, &[0x0F, 0x42, 0x40]), element(DURATION_ID, &100f32.to_bits().to_be_bytes())].concat());
+        [element(EBML_ID, &element(0x4282, b"webm")), vec![0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF], info, element(TRACKS_ID, &entry)].concat()
+    }
+
+    /// Cluster with an unknown size holding SimpleBlocks at the given timecodes
+    fn cluster(timecode: u8, blocks: &[(i16, bool)]) -> Vec<u8> {
+        let mut out = vec![0x1F, 0x43, 0xB6, 0x75, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        out.extend(element(TIMECODE_ID, &[timecode]));
+        for &(relative, key) in blocks {
+            let mut body = vec![0x81];
+            body.extend_from_slice(&relative.to_be_bytes());
+            body.push(if key { 0x80 } else { 0 });
+            body.extend_from_slice(&[0xAB; 5]);
+            out.extend(element(SIMPLE_BLOCK_ID, &body));
+        }
+        out
+    }
+
+    #[test]
+    fn test_ebml_id() { assert_eq!(EBML_ID, 0x1A45DFA3); }
+
+    #[test]
+    fn test_tracks_and_blocks() {
+        let file = [header(), cluster(0, &[(0, true), (33, false)]), cluster(66, &[(0, false), (34, true)])].concat();
+        let mut d = WebMDemuxer::new(file).unwrap();
+        let track = d.video_track().unwrap();
+        assert_eq!((track.codec, track.width, track.height), (CodecId::Vp9, Some(320), Some(240)));
+        assert_eq!(d.duration(), Some(Duration::from_millis(100)));
+        let p = d.read_packet().unwrap();
+        assert_eq!((p.pts, p.is_key, p.data.len()), (Duration::ZERO, true, 5));
+        assert_eq!(d.read_packet().unwrap().pts, Duration::from_millis(33));
+
+        d.seek(Duration::from_millis(90)).unwrap();
+        assert_eq!(d.read_packet().unwrap().pts, Duration::ZERO);
+        d.seek(Duration::from_millis(100)).unwrap();
+        assert_eq!(d.read_packet().unwrap().pts, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_incremental_append() {
+        let stream = [header(), cluster(0, &[(0, true), (33, false)])].concat();
+        let mut d = WebMDemuxer::streaming();
+        let mut packets = Vec::new();
+        for chunk in stream.chunks(5) {
+            packets.extend(d.append(chunk).unwrap());
+        }
+        assert!(d.has_init_segment());
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[1].duration, Duration::from_nanos(33_333_333));
+    }
+
+    #[test]
+    fn test_xiph_lacing() {
+        let mut d = WebMDemuxer::new(header()).unwrap();
+        let mut body = vec![0x81, 0, 0, 0x80 | 0x02, 2, 2, 3];
+        body.extend_from_slice(&[1, 1, 2, 2, 2, 3]);
+        let packets = d.append(&element(SIMPLE_BLOCK_ID, &body)).unwrap();
+        assert_eq!(packets.iter().map(|p| p.data.len()).collect::<Vec<_>>(), vec![2, 3, 1]);
+
+        // EBML lacing with a frame larger than the block
+        let body = [0x81, 0, 0, 0x80 | 0x06, 1, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE, 1, 2];
+        assert!(d.append(&element(SIMPLE_BLOCK_ID, &body)).is_err());
+    }
+
+    #[test]
+    fn test_muxer_round_trip() {
+        let track = |track_id, track_type, codec| TrackInfo {
+            track_id, track_type, codec, duration: Duration::ZERO, timescale: 1000,
+            width: Some(640), height: Some(480), frame_rate: None, sample_rate: Some(48000),
+            channels: Some(2), codec_private: Vec::new(), encryption: None,
+        };
+        let mut muxer = WebMMuxer::new(vec![track(1, TrackType::Video, CodecId::Vp8), track(2, TrackType::Audio, CodecId::Opus)]).unwrap();
+        assert!(WebMMuxer::new(vec![track(1, TrackType::Video, CodecId::H264)]).is_err());
+
+        let mut file = muxer.header();
+        file.extend(muxer.write_block(1, 0, true, &[1; 3]));
+        file.extend(muxer.write_block(2, 10, true, &[2]));
+        file.extend(muxer.write_block(1, 33, false, &[3; 4]));
+        file.extend(muxer.write_block(2, 40_000, true, &[4]));
+
+        let mut d = WebMDemuxer::new(file).unwrap();
+        let video = d.video_track().unwrap();
+        assert_eq!((video.codec, video.width, video.height), (CodecId::Vp8, Some(640), Some(480)));
+        let audio = d.audio_track().unwrap();
+        assert_eq!((audio.codec, audio.sample_rate, audio.channels), (CodecId::Opus, Some(48000), Some(2)));
+        let packets: Vec<(u32, u64, bool, usize)> = std::iter::from_fn(|| d.read_packet().ok())
+            .map(|p| (p.track_id, p.pts.as_millis() as u64, p.is_key, p.data.len()))
+            .collect();
+        assert_eq!(packets, vec![(1, 0, true, 3), (2, 10, true, 1), (1, 33, false, 4), (2, 40_000, true, 1)]);
+    }
+
+    #[test]
+    fn test_not_ebml() {
+        assert!(WebMDemuxer::new(vec![0x80, 0x80]).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! Accessibility Panel
+//!
+//! The accessibility tree beside the DOM inspector: each node's computed
+//! role, name, description and states, the audit findings on it, and the
+//! order a screen reader reads the page in. The embedder converts its
+//! accessibility tree into [`AxNode`]s, linked to inspector nodes by
+//! `dom_node`.
+
+use std::collections::HashMap;
+
+/// Severity of an accessibility finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AxIssueSeverity {
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl AxIssueSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+/// An audit finding on a node
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxIssue {
+    pub severity: AxIssueSeverity,
+    /// What is wrong and how to fix it
+    pub message: String,
+    /// WCAG success criterion, e.g. "1.1.1 Non-text Content"
+    pub wcag: String,
+}
+
+/// Accessibility node
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxNode {
+    pub id: u64,
+    /// Inspector node the accessibility node was built from
+    pub dom_node: Option<u64>,
+    /// Computed ARIA role, e.g. "button"
+    pub role: String,
+    /// Computed accessible name
+    pub name: String,
+    pub description: String,
+    pub value: Option<String>,
+    /// States such as "focusable", "expanded" or "disabled"
+    pub states: Vec<String>,
+    /// Hidden from assistive technology, with its subtree
+    pub ignored: bool,
+    pub parent: Option<u64>,
+    pub children: Vec<u64>,
+    pub issues: Vec<AxIssue>,
+}
+
+impl AxNode {
+    pub fn new(id: u64, role: &str) -> Self {
+        Self {
+            id,
+            dom_node: None,
+            role: role.to_string(),
+            name: String::new(),
+            description: String::new(),
+            value: None,
+            states: Vec::new(),
+            ignored: false,
+            parent: None,
+            children: Vec::new(),
+            issues: Vec::new(),
+        }
+    }
+    
+    /// What a screen reader says on reaching the node: name, role, value
+    /// and states, e.g. "Submit, button, disabled"
+    pub fn announcement(&self) -> String {
+        let mut parts: Vec<&str> = vec![&self.name, &self.role];
+        parts.extend(self.value.as_deref());
+        parts.extend(self.states.iter().map(String::as_str).filter(|s| *s != "focusable"));
+        parts.retain(|p| !p.is_empty());
+        parts.join(", ")
+    }
+    
+    /// Whether a screen reader skips the node while reading, though not
+    /// its children: unnamed generic containers
+    fn is_silent(&self) -> bool {
+        self.name.is_empty() && matches!(self.role.as_str(), "generic" | "none" | "presentation" | "document")
+    }
+}
+
+/// Accessibility panel
+#[derive(Debug, Default)]
+pub struct AccessibilityPanel {
+    nodes: HashMap<u64, AxNode>,
+    root: Option<u64>,
+    /// Position of the simulated screen reader in the reading order
+    cursor: Option<usize>,
+}
+
+impl AccessibilityPanel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Replace the tree; parents and children are taken from the nodes
+    pub fn set_tree(&mut self, root: u64, nodes: Vec<AxNode>) {
+        self.nodes = nodes.into_iter().map(|n| (n.id, n)).collect();
+        self.root = Some(root);
+        self.cursor = None;
+    }
+    
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.root = None;
+        self.cursor = None;
+    }
+    
+    pub fn root(&self) -> Option<&AxNode> {
+        self.nodes.get(&self.root?)
+    }
+    
+    pub fn node(&self, id: u64) -> Option<&AxNode> {
+        self.nodes.get(&id)
+    }
+    
+    /// Accessibility node of an inspector node, for showing it beside the
+    /// DOM tree
+    pub fn node_for_dom(&self, dom_node: u64) -> Option<&AxNode> {
+        self.nodes.values().find(|n| n.dom_node == Some(dom_node))
+    }
+    
+    /// Attach a finding to a node; false if there is no such node
+    pub fn add_issue(&mut self, id: u64, issue: AxIssue) -> bool {
+        match self.nodes.get_mut(&id) {
+            Some(node) => {
+                node.issues.push(issue);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Every finding with its node, most severe first, then in tree order
+    pub fn issues(&self) -> Vec<(&AxNode, &AxIssue)> {
+        let mut issues: Vec<(&AxNode, &AxIssue)> = self.tree_order().into_iter()
+            .filter_map(|id| self.nodes.get(&id))
+            .flat_map(|node| node.issues.iter().map(move |issue| (node, issue)))
+            .collect();
+        issues.sort_by_key(|(_, issue)| std::cmp::Reverse(issue.severity));
+        issues
+    }
+    
+    /// Node IDs in document order, ignored ones included
+    pub fn tree_order(&self) -> Vec<u64> {
+        let mut order = Vec::new();
+        let mut stack: Vec<u64> = self.root.into_iter().collect();
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.nodes.get(&id) {
+                order.push(id);
+                stack.extend(node.children.iter().rev());
+            }
+        }
+        order
+    }
+    
+    /// Render the tree as indented text, one node per line, with its
+    /// findings inline below it
+    pub fn render_tree(&self) -> String {
+        let mut out = String::new();
+        if let Some(root) = self.root {
+            self.render_node(root, 0, &mut out);
+        }
+        out
+    }
+    
+    fn render_node(&self, id: u64, depth: usize, out: &mut String) {
+        let Some(node) = self.nodes.get(&id) else { return };
+        let indent = "  ".repeat(depth);
+        out.push_str(&indent);
+        if node.ignored {
+            out.push_str("(ignored) ");
+        }
+        out.push_str(&node.role);
+        if !node.name.is_empty() {
+            out.push_str(&format!(" \"{}\"", node.name));
+        }
+        if let Some(value) = &node.value {
+            out.push_str(&format!(" = \"{}\"", value));
+        }
+        if !node.states.is_empty() {
+            out.push_str(&format!(" [{}]", node.states.join(", ")));
+        }
+        out.push('\n');
+        for issue in &node.issues {
+            out.push_str(&format!("{}  ! {}: {} ({})\n", indent, issue.severity.as_str(), issue.message, issue.wcag));
+        }
+        for child in &node.children {
+            self.render_node(*child, depth + 1, out);
+        }
+    }
+    
+    /// Node IDs in the order a screen reader reads them: document order,
+    /// leaving out ignored subtrees and unnamed generic containers
+    pub fn reading_order(&self) -> Vec<u64> {
+        let mut order = Vec::new();
+        let mut stack: Vec<u64> = self.root.into_iter().collect();
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.nodes.get(&id) else { continue };
+            if node.ignored {
+                continue;
+            }
+            if !node.is_silent() {
+                order.push(id);
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        order
+    }
+    
+    // === Screen Reader Simulation ===
+    
+    /// Node the simulated screen reader is on
+    pub fn reader_node(&self) -> Option<&AxNode> {
+        let id = *self.reading_order().get(self.cursor?)?;
+        self.nodes.get(&id)
+    }
+    
+    /// Move to the next node in reading order, from the start when nothing
+    /// has been read yet; returns its announcement
+    pub fn reader_next(&mut self) -> Option<String> {
+        let next = self.cursor.map_or(0, |c| c + 1);
+        self.reader_move(next)
+    }
+    
+    /// Move to the previous node in reading order
+    pub fn reader_prev(&mut self) -> Option<String> {
+        let prev = self.cursor?.checked_sub(1)?;
+        self.reader_move(prev)
+    }
+    
+    /// Move to the next node with a role, as a screen reader's quick
+    /// navigation keys do (H for headings, K for links)
+    pub fn reader_next_role(&mut self, role: &str) -> Option<String> {
+        let order = self.reading_order();
+        let start = self.cursor.map_or(0, |c| c + 1);
+        let next = (start..order.len()).find(|&i| self.nodes[&order[i]].role == role)?;
+        self.reader_move(next)
+    }
+    
+    /// Go back to before the first node
+    pub fn reader_reset(&mut self) {
+        self.cursor = None;
+    }
+    
+    fn reader_move(&mut self, index: usize) -> Option<String> {
+        let id = *self.reading_order().get(index)?;
+        self.cursor = Some(index);
+        self.nodes.get(&id).map(AxNode::announcement)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn node(id: u64, parent: Option<u64>, role: &str, name: &str, children: &[u64]) -> AxNode {
+        let mut node = AxNode::new(id, role);
+        node.name = name.to_string();
+        node.parent = parent;
+        node.children = children.to_vec();
+        node
+    }
+    
+    #[test]
+    fn test_screen_reader_order() {
+        let mut panel = AccessibilityPanel::new();
+        let mut hidden = node(5, Some(2), "button", "Hidden", &[]);
+        hidden.ignored = true;
+        let mut submit = node(4, Some(2), "button", "Submit", &[]);
+        submit.states = vec!["focusable".to_string(), "disabled".to_string()];
+        submit.dom_node = Some(40);
+        panel.set_tree(1, vec![
+            node(1, None, "document", "", &[2, 6]),
+            node(2, Some(1), "generic", "", &[3, 4, 5]),
+            node(3, Some(2), "heading", "Sign in", &[]),
+            submit,
+            hidden,
+            node(6, Some(1), "link", "Help", &[]),
+        ]);
+        
+        assert_eq!(panel.tree_order(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(panel.reading_order(), vec![3, 4, 6]);
+        assert_eq!(panel.node_for_dom(40).map(|n| n.id), Some(4));
+        
+        assert_eq!(panel.reader_next().as_deref(), Some("Sign in, heading"));
+        assert_eq!(panel.reader_next().as_deref(), Some("Submit, button, disabled"));
+        assert_eq!(panel.reader_prev().as_deref(), Some("Sign in, heading"));
+        assert_eq!(panel.reader_next_role("link").as_deref(), Some("Help, link"));
+        assert_eq!(panel.reader_next(), None);
+        assert_eq!(panel.reader_node().map(|n| n.id), Some(6));
+        
+        assert!(panel.add_issue(6, AxIssue {
+            severity: AxIssueSeverity::Warning,
+            message: "Link text is not descriptive".to_string(),
+            wcag: "2.4.4 Link Purpose".to_string(),
+        }));
+        assert!(panel.add_issue(4, AxIssue {
+            severity: AxIssueSeverity::Error,
+            message: "Add aria-label".to_string(),
+            wcag: "4.1.2 Name, Role, Value".to_string(),
+        }));
+        assert_eq!(panel.issues()[0].0.id, 4);
+        let rendered = panel.render_tree();
+        assert!(rendered.contains("    button \"Submit\" [focusable, disabled]\n      ! error: Add aria-label (4.1.2 Name, Role, Value)\n"));
+        assert!(rendered.contains("    (ignored) button \"Hidden\"\n"));
+    }
+}
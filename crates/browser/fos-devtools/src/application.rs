@@ -2,6 +2,8 @@
 //!
 //! Service Worker status, manifest viewer, and PWA installation status.
 
+use crate::cdp::serde_value::Value;
+
 /// Service Worker state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ServiceWorkerState { Parsed, Installing, Installed, Activating, Activated, Redundant }
@@ -33,6 +35,83 @@ pub struct WebAppManifest {
     pub icons: Vec<ManifestIcon>,
     pub categories: Vec<String>,
     pub lang: Option<String>,
+    pub shortcuts: Vec<ManifestShortcut>,
+}
+
+impl WebAppManifest {
+    /// Parse manifest JSON; None if it is not a JSON object. URLs are left
+    /// as written, relative to the manifest.
+    pub fn from_json(text: &str) -> Option<Self> {
+        let json = Value::from_json(text)?;
+        if !matches!(json, Value::Map(_)) { return None; }
+        let string = |key: &str| json.get(key).and_then(Value::as_str).map(String::from);
+        Some(Self {
+            name: string("name"),
+            short_name: string("short_name"),
+            description: string("description"),
+            start_url: string("start_url"),
+            scope: string("scope"),
+            display: string("display").map_or_else(DisplayMode::default, |d| DisplayMode::parse(&d)),
+            orientation: string("orientation").map_or_else(Orientation::default, |o| Orientation::parse(&o)),
+            theme_color: string("theme_color"),
+            background_color: string("background_color"),
+            icons: parse_icons(json.get("icons")),
+            categories: items(json.get("categories")).filter_map(Value::as_str).map(String::from).collect(),
+            lang: string("lang"),
+            // Shortcuts need a name and a URL
+            shortcuts: items(json.get("shortcuts")).filter_map(|item| Some(ManifestShortcut {
+                name: item.get("name")?.as_str()?.to_string(),
+                short_name: item.get("short_name").and_then(Value::as_str).map(String::from),
+                description: item.get("description").and_then(Value::as_str).map(String::from),
+                url: item.get("url")?.as_str()?.to_string(),
+                icons: parse_icons(item.get("icons")),
+            })).collect(),
+        })
+    }
+}
+
+/// Elements of a JSON array; nothing for anything else
+fn items(value: Option<&Value>) -> impl Iterator<Item = &Value> {
+    let items: &[Value] = match value {
+        Some(Value::Seq(items)) => items,
+        _ => &[],
+    };
+    items.iter()
+}
+
+/// Icons with a `src`; sizes like "48x48 96x96", skipping "any"
+fn parse_icons(value: Option<&Value>) -> Vec<ManifestIcon> {
+    items(value).filter_map(|icon| {
+        let sizes = icon.get("sizes").and_then(Value::as_str).unwrap_or("")
+            .split_whitespace()
+            .filter_map(|size| {
+                let (w, h) = size.to_ascii_lowercase().split_once('x').map(|(w, h)| (w.parse().ok(), h.parse().ok()))?;
+                Some((w?, h?))
+            })
+            .collect();
+        // The first purpose understood wins
+        let purpose = icon.get("purpose").and_then(Value::as_str).unwrap_or("")
+            .split_whitespace()
+            .find_map(|p| match p { "any" => Some(IconPurpose::Any), "monochrome" => Some(IconPurpose::Monochrome),
+                                    "maskable" => Some(IconPurpose::Maskable), _ => None })
+            .unwrap_or_default();
+        Some(ManifestIcon {
+            src: icon.get("src")?.as_str()?.to_string(),
+            sizes,
+            icon_type: icon.get("type").and_then(Value::as_str).map(String::from),
+            purpose,
+        })
+    }).collect()
+}
+
+/// App shortcut, offered in the launcher or jump list of an installed app
+#[derive(Debug, Clone)]
+pub struct ManifestShortcut {
+    pub name: String,
+    pub short_name: Option<String>,
+    pub description: Option<String>,
+    pub url: String,
+    pub icons: Vec<ManifestIcon>,
 }
 
 /// Display mode
@@ -51,6 +130,14 @@ impl DisplayMode {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Orientation { #[default] Any, Natural, Landscape, Portrait, LandscapePrimary, PortraitPrimary }
 
+impl Orientation {
+    pub fn parse(s: &str) -> Self {
+        match s { "natural" => Self::Natural, "landscape" => Self::Landscape, "portrait" => Self::Portrait,
+                  "landscape-primary" => Self::LandscapePrimary, "portrait-primary" => Self::PortraitPrimary,
+                  _ => Self::Any }
+    }
+}
+
 /// Manifest icon
 #[derive(Debug, Clone)]
 pub struct ManifestIcon {
@@ -142,4 +229,22 @@ mod tests {
         let status = PwaStatus::check(Some(&manifest), Some(&sw), true);
         assert!(status.installable);
     }
+    
+    #[test]
+    fn test_manifest_from_json() {
+        let manifest = WebAppManifest::from_json(r#"{
+            "name": "Mail", "start_url": "/inbox", "display": "standalone",
+            "icons": [{"src": "icon.png", "sizes": "48x48 192X192 any", "purpose": "maskable any"}],
+            "shortcuts": [
+                {"name": "Compose", "url": "/compose", "icons": [{"src": "compose.png"}]},
+                {"name": "No URL"}
+            ]
+        }"#).unwrap();
+        assert_eq!(manifest.display, DisplayMode::Standalone);
+        assert_eq!(manifest.icons[0].sizes, vec![(48, 48), (192, 192)]);
+        assert_eq!(manifest.icons[0].purpose, IconPurpose::Maskable);
+        assert_eq!(manifest.shortcuts.len(), 1);
+        assert_eq!((manifest.shortcuts[0].name.as_str(), manifest.shortcuts[0].url.as_str()), ("Compose", "/compose"));
+        assert!(WebAppManifest::from_json("[]").is_none());
+    }
 }
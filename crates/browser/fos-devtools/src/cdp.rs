@@ -1,10 +1,27 @@
 //! Chrome DevTools Protocol (CDP) Server
 //!
 //! Implements the CDP protocol for compatibility with Chrome DevTools frontend
-//! and other CDP clients.
+//! and other CDP clients. The DOM and CSS domains are served from the
+//! [`Inspector`], Network from the [`NetworkPanel`], Runtime and Console from
+//! the [`Console`], Debugger from the [`Debugger`], code coverage from the
+//! [`CoveragePanel`] and Accessibility from the [`AccessibilityPanel`]; the
+//! embedder keeps
+//! those panels up to date and calls [`CdpServer::collect_events`] to turn
+//! their changes into protocol events. [`crate::remote`] carries messages
+//! over a WebSocket.
 
 use std::collections::HashMap;
-use crate::{Console, ConsoleValue, Debugger, DebuggerState, PerformancePanel, NetworkPanel};
+use crate::{AccessibilityPanel, AxNode, Console, ConsoleMessage, ConsoleValue, CoveragePanel, CoverageType, Debugger, DebuggerState, Inspector, InspectedNode, LogLevel, NetworkPanel, NodeType, PerformancePanel};
+use crate::debugger::{CallFrame, ScopeType, VariableValue};
+use crate::network::{base64_encode, Cookie, NetworkThrottle, RequestStatus, SameSite};
+use crate::sources::SourceFile;
+use self::serde_value::{object, Value};
+
+/// Frame ID of the inspected page
+const MAIN_FRAME: &str = "main";
+
+/// Execution context of the inspected page
+const MAIN_CONTEXT: u64 = 1;
 
 /// CDP server for handling DevTools protocol communication
 #[derive(Debug)]
@@ -15,8 +32,49 @@ pub struct CdpServer {
     next_session_id: u64,
     /// Event listeners
     enabled_domains: Vec<String>,
+    /// Session each domain was enabled from, which its events are sent to
+    domain_sessions: HashMap<String, Option<String>>,
+    /// Session of the command being handled
+    current_session: Option<String>,
     /// Pending events
     pending_events: Vec<CdpEvent>,
+    /// Console messages for the Runtime and Console domains
+    pub console: Console,
+    /// DOM tree and styles for the DOM and CSS domains
+    pub inspector: Inspector,
+    /// Requests and cookies for the Network domain
+    pub network: NetworkPanel,
+    /// Breakpoints and call stack for the Debugger domain
+    pub debugger: Debugger,
+    /// Pipeline trace events for the Tracing domain
+    pub performance: PerformancePanel,
+    /// Rule usage and function coverage for the CSS and Profiler domains
+    pub coverage: CoveragePanel,
+    /// Accessibility tree for the Accessibility domain
+    pub accessibility: AccessibilityPanel,
+    /// Parsed scripts; the script ID is the index
+    scripts: Vec<SourceFile>,
+    page_url: String,
+    page_title: String,
+    /// Navigations requested by clients, for the embedder to perform
+    navigations: Vec<String>,
+    cache_disabled: bool,
+    /// Console values handed out as remote object IDs
+    remote_objects: HashMap<String, ConsoleValue>,
+    next_object_id: u64,
+    /// `Console::logged_count` when messages were last reported
+    console_reported: u64,
+    /// Last lifecycle stage reported for each network request
+    network_reported: HashMap<u64, NetworkStage>,
+    /// Debugger state when it was last reported
+    debugger_reported: DebuggerState,
+}
+
+/// Network request lifecycle stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum NetworkStage {
+    Sent,
+    Finished,
 }
 
 impl Default for CdpServer {
@@ -31,10 +89,29 @@ impl CdpServer {
             sessions: HashMap::new(),
             next_session_id: 0,
             enabled_domains: Vec::new(),
+            domain_sessions: HashMap::new(),
+            current_session: None,
             pending_events: Vec::new(),
+            console: Console::new(),
+            inspector: Inspector::new(),
+            network: NetworkPanel::new(),
+            debugger: Debugger::new(),
+            performance: PerformancePanel::new(),
+            coverage: CoveragePanel::new(),
+            accessibility: AccessibilityPanel::new(),
+            scripts: Vec::new(),
+            page_url: "about:blank".to_string(),
+            page_title: String::new(),
+            navigations: Vec::new(),
+            cache_disabled: false,
+            remote_objects: HashMap::new(),
+            next_object_id: 0,
+            console_reported: 0,
+            network_reported: HashMap::new(),
+            debugger_reported: DebuggerState::Running,
         }
     }
-    
+
     /// Create a new session
     pub fn create_session(&mut self) -> String {
         let id = format!("session-{}", self.next_session_id);
@@ -42,351 +119,1438 @@ impl CdpServer {
         self.sessions.insert(id.clone(), CdpSession::new(id.clone()));
         id
     }
-    
+
     /// Close a session
     pub fn close_session(&mut self, session_id: &str) {
         self.sessions.remove(session_id);
+        self.domain_sessions.retain(|_, s| s.as_deref() != Some(session_id));
     }
-    
+
     /// Handle incoming CDP command
     pub fn handle_command(&mut self, cmd: CdpCommand) -> CdpResponse {
-        let method = cmd.method.as_str();
-        
+        if let Some(session) = &cmd.session_id {
+            if !self.sessions.contains_key(session) {
+                let mut response = CdpResponse::error(cmd.id, CdpError::session_not_found(session));
+                response.session_id = cmd.session_id.clone();
+                return response;
+            }
+        }
+        self.current_session = cmd.session_id.clone();
+
         // Route to appropriate domain handler
-        if method.starts_with("Runtime.") {
-            self.handle_runtime(cmd)
-        } else if method.starts_with("Debugger.") {
-            self.handle_debugger(cmd)
-        } else if method.starts_with("Network.") {
-            self.handle_network(cmd)
-        } else if method.starts_with("DOM.") {
-            self.handle_dom(cmd)
-        } else if method.starts_with("CSS.") {
-            self.handle_css(cmd)
-        } else if method.starts_with("Page.") {
-            self.handle_page(cmd)
-        } else if method.starts_with("Console.") {
-            self.handle_console(cmd)
-        } else if method.starts_with("Profiler.") {
-            self.handle_profiler(cmd)
-        } else if method.starts_with("HeapProfiler.") {
-            self.handle_heap_profiler(cmd)
-        } else if method.starts_with("Target.") {
-            self.handle_target(cmd)
-        } else {
-            CdpResponse::error(cmd.id, CdpError::method_not_found(&cmd.method))
-        }
-    }
-    
+        let domain = cmd.method.split('.').next().unwrap_or("");
+        let result = match domain {
+            "Runtime" => self.handle_runtime(&cmd),
+            "Debugger" => self.handle_debugger(&cmd),
+            "Network" => self.handle_network(&cmd),
+            "DOM" => self.handle_dom(&cmd),
+            "CSS" => self.handle_css(&cmd),
+            "Overlay" => self.handle_overlay(&cmd),
+            "Page" => self.handle_page(&cmd),
+            "Console" => self.handle_console(&cmd),
+            "Profiler" => self.handle_profiler(&cmd),
+            "HeapProfiler" => self.handle_heap_profiler(&cmd),
+            "Tracing" => self.handle_tracing(&cmd),
+            "Accessibility" => self.handle_accessibility(&cmd),
+            "Target" => self.handle_target(&cmd),
+            "Browser" => self.handle_browser(&cmd),
+            _ => Err(CdpError::method_not_found(&cmd.method)),
+        };
+        self.current_session = None;
+
+        let mut response = match result {
+            Ok(result) => CdpResponse::success(cmd.id, result),
+            Err(error) => CdpResponse::error(cmd.id, error),
+        };
+        response.session_id = cmd.session_id;
+        response
+    }
+
     /// Enable a domain
     pub fn enable_domain(&mut self, domain: &str) {
         if !self.enabled_domains.contains(&domain.to_string()) {
             self.enabled_domains.push(domain.to_string());
         }
+        self.domain_sessions.insert(domain.to_string(), self.current_session.clone());
     }
-    
+
     /// Disable a domain
     pub fn disable_domain(&mut self, domain: &str) {
         self.enabled_domains.retain(|d| d != domain);
+        self.domain_sessions.remove(domain);
     }
-    
+
     /// Check if domain is enabled
     pub fn is_domain_enabled(&self, domain: &str) -> bool {
         self.enabled_domains.contains(&domain.to_string())
     }
-    
+
     /// Emit an event
     pub fn emit_event(&mut self, event: CdpEvent) {
         self.pending_events.push(event);
     }
-    
+
+    /// Emit an event of an enabled domain to the session that enabled it
+    fn emit_domain_event(&mut self, mut event: CdpEvent) {
+        let domain = event.method.split('.').next().unwrap_or("");
+        if let Some(session) = self.domain_sessions.get(domain) {
+            event.session_id = session.clone();
+            self.pending_events.push(event);
+        }
+    }
+
     /// Get and clear pending events
     pub fn take_events(&mut self) -> Vec<CdpEvent> {
         std::mem::take(&mut self.pending_events)
     }
-    
+
+    // === Embedder API ===
+
+    /// URL of the inspected page
+    pub fn page_url(&self) -> &str {
+        &self.page_url
+    }
+
+    /// Title of the inspected page
+    pub fn page_title(&self) -> &str {
+        &self.page_title
+    }
+
+    /// Record a committed navigation; clients see a new frame, execution
+    /// context and document
+    pub fn set_page(&mut self, url: &str, title: &str) {
+        self.page_url = url.to_string();
+        self.page_title = title.to_string();
+        self.remote_objects.clear();
+        let frame = self.frame_value();
+        self.emit_domain_event(CdpEvent::new("Page.frameNavigated", object([("frame", frame), ("type", "Navigation".into())])));
+        self.emit_domain_event(CdpEvent::new("Runtime.executionContextsCleared", object([])));
+        let context = self.execution_context();
+        self.emit_domain_event(CdpEvent::new("Runtime.executionContextCreated", object([("context", context)])));
+        self.emit_domain_event(CdpEvent::new("DOM.documentUpdated", object([])));
+    }
+
+    /// Take the navigations clients asked for with `Page.navigate` and
+    /// `Page.reload`
+    pub fn take_navigations(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.navigations)
+    }
+
+    /// Whether a client disabled the HTTP cache
+    pub fn cache_disabled(&self) -> bool {
+        self.cache_disabled
+    }
+
+    /// Register a parsed script and return its script ID
+    pub fn add_script(&mut self, script: SourceFile) -> String {
+        let id = self.scripts.len().to_string();
+        self.scripts.push(script);
+        let event = self.script_parsed(self.scripts.len() - 1);
+        self.emit_domain_event(event);
+        id
+    }
+
+    /// Queue events for what changed in the panels since the last call:
+    /// console messages, network request progress and debugger pauses
+    pub fn collect_events(&mut self) {
+        // Console messages; ones dropped from the console's buffer are lost
+        let logged = self.console.logged_count();
+        if logged > self.console_reported {
+            let messages = self.console.get_messages();
+            let new = ((logged - self.console_reported) as usize).min(messages.len());
+            let new: Vec<ConsoleMessage> = messages.iter().skip(messages.len() - new).cloned().collect();
+            self.console_reported = logged;
+            for message in &new {
+                self.report_console_message(message);
+            }
+        }
+
+        // Network request lifecycle
+        if self.is_domain_enabled("Network") {
+            let mut events = Vec::new();
+            for request in self.network.get_requests() {
+                let stage = self.network_reported.get(&request.id).copied();
+                if stage.is_none() {
+                    events.push(network_request_event(request, &self.page_url));
+                    self.network_reported.insert(request.id, NetworkStage::Sent);
+                }
+                if stage == Some(NetworkStage::Finished) {
+                    continue;
+                }
+                let timestamp = request.timing.receive_end.unwrap_or(request.timing.start_time) as f64 / 1000.0;
+                let request_id = Value::from(request.id.to_string());
+                match &request.status {
+                    RequestStatus::Pending => continue,
+                    RequestStatus::Complete { .. } => {
+                        let response = self.network.get_response(request.id);
+                        let mime_type = response.and_then(|r| r.content_type.as_deref()).unwrap_or("");
+                        let mime_type = mime_type.split(';').next().unwrap_or("").trim().to_string();
+                        if let Some(response) = response {
+                            let headers = object(response.headers.iter().map(|(k, v)| (k.as_str(), Value::from(v.as_str()))));
+                            events.push(CdpEvent::new("Network.responseReceived", object([
+                                ("requestId", request_id.clone()),
+                                ("loaderId", MAIN_FRAME.into()),
+                                ("frameId", MAIN_FRAME.into()),
+                                ("timestamp", timestamp.into()),
+                                ("type", resource_type(&mime_type).into()),
+                                ("response", object([
+                                    ("url", request.url.as_str().into()),
+                                    ("status", (response.status_code as u64).into()),
+                                    ("statusText", response.status_text.as_str().into()),
+                                    ("headers", headers),
+                                    ("mimeType", mime_type.as_str().into()),
+                                    ("encodedDataLength", (response.content_length.unwrap_or(0) as u64).into()),
+                                ])),
+                            ])));
+                        }
+                        let length = response.and_then(|r| r.body.as_ref().map(Vec::len).or(r.content_length)).unwrap_or(0);
+                        events.push(CdpEvent::new("Network.loadingFinished", object([
+                            ("requestId", request_id),
+                            ("timestamp", timestamp.into()),
+                            ("encodedDataLength", (length as u64).into()),
+                        ])));
+                    }
+                    RequestStatus::Failed { error } => {
+                        events.push(loading_failed(request_id, timestamp, error, false));
+                    }
+                    RequestStatus::Cancelled => {
+                        events.push(loading_failed(request_id, timestamp, "net::ERR_ABORTED", true));
+                    }
+                }
+                self.network_reported.insert(request.id, NetworkStage::Finished);
+            }
+            for event in events {
+                self.emit_domain_event(event);
+            }
+        }
+
+        // Debugger pauses and resumptions
+        let state = self.debugger.state();
+        if state != self.debugger_reported {
+            if state == DebuggerState::Paused {
+                let event = self.paused_event();
+                self.emit_domain_event(event);
+            } else if self.debugger_reported == DebuggerState::Paused {
+                self.emit_domain_event(CdpEvent::debugger_resumed());
+            }
+            self.debugger_reported = state;
+        }
+    }
+
+    fn report_console_message(&mut self, message: &ConsoleMessage) {
+        let (kind, level) = match message.level {
+            LogLevel::Log => ("log", "log"),
+            LogLevel::Info => ("info", "info"),
+            LogLevel::Warn => ("warning", "warning"),
+            LogLevel::Error => ("error", "error"),
+            LogLevel::Debug => ("debug", "debug"),
+            LogLevel::Trace => ("trace", "log"),
+        };
+        let url = message.source.as_ref().map(|s| s.url.as_str());
+        let line = message.source.as_ref().map(|s| s.line);
+        self.emit_domain_event(CdpEvent::console_message_added(level, &message.message, url, line));
+
+        if self.is_domain_enabled("Runtime") {
+            let mut args = vec![self.remote_object(&ConsoleValue::String(message.message.clone()))];
+            for arg in &message.args {
+                args.push(self.remote_object(arg));
+            }
+            let mut params = vec![
+                ("type", Value::from(kind)),
+                ("args", Value::Seq(args)),
+                ("executionContextId", MAIN_CONTEXT.into()),
+                ("timestamp", (message.timestamp as f64).into()),
+            ];
+            if let Some(frames) = &message.stack_trace {
+                let frames = frames.iter().map(|f| object([
+                    ("functionName", f.function_name.as_str().into()),
+                    ("scriptId", self.script_id(&f.url).into()),
+                    ("url", f.url.as_str().into()),
+                    ("lineNumber", (f.line as u64).into()),
+                    ("columnNumber", (f.column as u64).into()),
+                ])).collect();
+                params.push(("stackTrace", object([("callFrames", Value::Seq(frames))])));
+            }
+            self.emit_domain_event(CdpEvent::new("Runtime.consoleAPICalled", object(params)));
+        }
+    }
+
     // === Domain Handlers ===
-    
-    fn handle_runtime(&mut self, cmd: CdpCommand) -> CdpResponse {
+
+    fn handle_runtime(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
         match cmd.method.as_str() {
             "Runtime.enable" => {
                 self.enable_domain("Runtime");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                let context = self.execution_context();
+                self.emit_domain_event(CdpEvent::new("Runtime.executionContextCreated", object([("context", context)])));
+                self.replay_console();
+                Ok(object([]))
             }
             "Runtime.disable" => {
                 self.disable_domain("Runtime");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "Runtime.evaluate" => {
-                let expression = cmd.params.get("expression")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-                
-                // Placeholder evaluation result
-                let result = RuntimeRemoteObject {
-                    object_type: "undefined".to_string(),
-                    value: None,
-                    description: Some("undefined".to_string()),
-                    object_id: None,
-                };
-                
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("result".into(), result.to_value()),
-                ].into_iter().collect()))
+                let expression = cmd.str_param("expression")?;
+                Ok(self.evaluate(expression, None))
             }
             "Runtime.getProperties" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("result".into(), serde_value::Value::Seq(vec![])),
-                ].into_iter().collect()))
+                let object_id = cmd.str_param("objectId")?;
+                let properties = self.properties(object_id)
+                    .ok_or_else(|| CdpError::server_error("Could not find object with given id"))?;
+                Ok(object([("result", Value::Seq(properties))]))
+            }
+            "Runtime.releaseObject" => {
+                self.remote_objects.remove(cmd.str_param("objectId")?);
+                Ok(object([]))
             }
-            _ => CdpResponse::error(cmd.id, CdpError::method_not_found(&cmd.method)),
+            "Runtime.releaseObjectGroup" => {
+                self.remote_objects.clear();
+                Ok(object([]))
+            }
+            "Runtime.runIfWaitingForDebugger" => Ok(object([])),
+            _ => Err(CdpError::method_not_found(&cmd.method)),
         }
     }
-    
-    fn handle_debugger(&mut self, cmd: CdpCommand) -> CdpResponse {
+
+    fn handle_debugger(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
         match cmd.method.as_str() {
             "Debugger.enable" => {
                 self.enable_domain("Debugger");
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("debuggerId".into(), serde_value::Value::String("fos-debugger".to_string())),
-                ].into_iter().collect()))
+                for index in 0..self.scripts.len() {
+                    let event = self.script_parsed(index);
+                    self.emit_domain_event(event);
+                }
+                if self.debugger.state() == DebuggerState::Paused {
+                    let event = self.paused_event();
+                    self.emit_domain_event(event);
+                }
+                self.debugger_reported = self.debugger.state();
+                Ok(object([("debuggerId", "fos-debugger".into())]))
             }
             "Debugger.disable" => {
                 self.disable_domain("Debugger");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "Debugger.setBreakpointByUrl" => {
-                let url = cmd.params.get("url").and_then(|v| v.as_str()).unwrap_or("");
-                let line = cmd.params.get("lineNumber").and_then(|v| v.as_u64()).unwrap_or(0);
-                
-                let bp_id = format!("bp-{}:{}", url, line);
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("breakpointId".into(), serde_value::Value::String(bp_id)),
-                    ("locations".into(), serde_value::Value::Seq(vec![])),
-                ].into_iter().collect()))
+                let url = cmd.str_param("url")?;
+                let line = cmd.u64_param("lineNumber")? as u32;
+                let id = match cmd.param("condition").and_then(Value::as_str).filter(|c| !c.is_empty()) {
+                    Some(condition) => self.debugger.add_conditional_breakpoint(url, line, condition),
+                    None => self.debugger.add_breakpoint(url, line),
+                };
+                let locations: Vec<Value> = self.scripts.iter().enumerate()
+                    .filter(|(_, s)| s.url == url)
+                    .map(|(i, _)| location(&i.to_string(), line, cmd.param("columnNumber").and_then(Value::as_u64).unwrap_or(0) as u32))
+                    .collect();
+                Ok(object([("breakpointId", id.to_string().into()), ("locations", Value::Seq(locations))]))
+            }
+            "Debugger.setBreakpoint" => {
+                let at = cmd.param("location").ok_or_else(|| CdpError::invalid_params("location is required"))?;
+                let script_id = at.get("scriptId").and_then(Value::as_str).unwrap_or("");
+                let url = self.script(script_id)?.url.clone();
+                let line = at.get("lineNumber").and_then(Value::as_u64)
+                    .ok_or_else(|| CdpError::invalid_params("lineNumber is required"))? as u32;
+                let column = at.get("columnNumber").and_then(Value::as_u64).unwrap_or(0) as u32;
+                let id = match cmd.param("condition").and_then(Value::as_str).filter(|c| !c.is_empty()) {
+                    Some(condition) => self.debugger.add_conditional_breakpoint(&url, line, condition),
+                    None => self.debugger.add_breakpoint(&url, line),
+                };
+                Ok(object([("breakpointId", id.to_string().into()), ("actualLocation", location(script_id, line, column))]))
             }
             "Debugger.removeBreakpoint" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                let id = cmd.str_param("breakpointId")?.parse()
+                    .map_err(|_| CdpError::invalid_params("Unknown breakpoint id"))?;
+                self.debugger.remove_breakpoint(id);
+                Ok(object([]))
+            }
+            "Debugger.setBreakpointsActive" => {
+                let active = cmd.param("active").and_then(Value::as_bool)
+                    .ok_or_else(|| CdpError::invalid_params("active is required"))?;
+                let ids: Vec<u64> = self.debugger.get_breakpoints().iter().map(|bp| bp.id).collect();
+                for id in ids {
+                    self.debugger.set_breakpoint_enabled(id, active);
+                }
+                Ok(object([]))
+            }
+            "Debugger.setPauseOnExceptions" => {
+                match cmd.str_param("state")? {
+                    "none" => self.debugger.set_pause_on_exceptions(false, false),
+                    "uncaught" => self.debugger.set_pause_on_exceptions(true, false),
+                    "caught" | "all" => self.debugger.set_pause_on_exceptions(true, true),
+                    _ => return Err(CdpError::invalid_params("Unknown pause on exceptions mode")),
+                }
+                Ok(object([]))
             }
             "Debugger.pause" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                self.debugger.pause();
+                Ok(object([]))
             }
             "Debugger.resume" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                self.debugger.resume();
+                Ok(object([]))
             }
             "Debugger.stepOver" | "Debugger.stepInto" | "Debugger.stepOut" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                if self.debugger.state() != DebuggerState::Paused {
+                    return Err(CdpError::server_error("Can only perform operation while paused."));
+                }
+                match cmd.method.as_str() {
+                    "Debugger.stepOver" => self.debugger.step_over(),
+                    "Debugger.stepInto" => self.debugger.step_into(),
+                    _ => self.debugger.step_out(),
+                }
+                Ok(object([]))
+            }
+            "Debugger.getScriptSource" => {
+                let script = self.script(cmd.str_param("scriptId")?)?;
+                Ok(object([("scriptSource", script.content.as_str().into())]))
+            }
+            "Debugger.evaluateOnCallFrame" => {
+                let frame = cmd.str_param("callFrameId")?.parse()
+                    .map_err(|_| CdpError::invalid_params("Invalid call frame id"))?;
+                let expression = cmd.str_param("expression")?;
+                Ok(self.evaluate(expression, Some(frame)))
             }
-            _ => CdpResponse::error(cmd.id, CdpError::method_not_found(&cmd.method)),
+            _ => Err(CdpError::method_not_found(&cmd.method)),
         }
     }
-    
-    fn handle_network(&mut self, cmd: CdpCommand) -> CdpResponse {
+
+    fn handle_network(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
         match cmd.method.as_str() {
             "Network.enable" => {
                 self.enable_domain("Network");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                self.network.start_recording();
+                // Only requests started from now on are reported
+                for request in self.network.get_requests() {
+                    self.network_reported.insert(request.id, NetworkStage::Finished);
+                }
+                Ok(object([]))
             }
             "Network.disable" => {
                 self.disable_domain("Network");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "Network.setCacheDisabled" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                self.cache_disabled = cmd.param("cacheDisabled").and_then(Value::as_bool)
+                    .ok_or_else(|| CdpError::invalid_params("cacheDisabled is required"))?;
+                Ok(object([]))
             }
             "Network.emulateNetworkConditions" => {
-                // Would apply throttle settings
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                let offline = cmd.param("offline").and_then(Value::as_bool).unwrap_or(false);
+                let latency = cmd.param("latency").and_then(Value::as_f64).unwrap_or(0.0);
+                // Throughputs are in bytes per second; -1 disables throttling
+                let throughput = |key: &str| match cmd.param(key).and_then(Value::as_f64) {
+                    Some(bps) if bps > 0.0 => Some(bps as u64),
+                    _ => None,
+                };
+                let (download, upload) = (throughput("downloadThroughput"), throughput("uploadThroughput"));
+                if offline {
+                    self.network.set_throttle(NetworkThrottle::OFFLINE);
+                } else if latency <= 0.0 && download.is_none() && upload.is_none() {
+                    self.network.clear_throttle();
+                } else {
+                    self.network.set_throttle(NetworkThrottle::custom(
+                        download.unwrap_or(u64::MAX),
+                        upload.unwrap_or(u64::MAX),
+                        latency.max(0.0) as u32,
+                    ));
+                }
+                Ok(object([]))
             }
             "Network.getCookies" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("cookies".into(), serde_value::Value::Seq(vec![])),
-                ].into_iter().collect()))
+                let hosts: Vec<String> = match cmd.param("urls") {
+                    Some(Value::Seq(urls)) => urls.iter().filter_map(Value::as_str).map(url_host).collect(),
+                    _ => Vec::new(),
+                };
+                let cookies = self.network.get_cookies().iter()
+                    .filter(|c| hosts.is_empty() || hosts.iter().any(|h| domain_matches(h, &c.domain)))
+                    .map(cookie_value)
+                    .collect();
+                Ok(object([("cookies", Value::Seq(cookies))]))
+            }
+            "Network.deleteCookies" => {
+                let name = cmd.str_param("name")?;
+                let domains: Vec<String> = match cmd.param("domain").and_then(Value::as_str) {
+                    Some(domain) => vec![domain.to_string()],
+                    None => self.network.get_cookies().iter().filter(|c| c.name == name).map(|c| c.domain.clone()).collect(),
+                };
+                for domain in domains {
+                    self.network.delete_cookie(name, &domain);
+                }
+                Ok(object([]))
+            }
+            "Network.clearBrowserCookies" => {
+                self.network.clear_cookies();
+                Ok(object([]))
+            }
+            "Network.getResponseBody" => {
+                let request_id: u64 = cmd.str_param("requestId")?.parse()
+                    .map_err(|_| CdpError::invalid_params("Invalid request id"))?;
+                let body = self.network.get_response(request_id)
+                    .and_then(|r| r.body.as_ref())
+                    .ok_or_else(|| CdpError::server_error("No resource with given identifier found"))?;
+                Ok(match std::str::from_utf8(body) {
+                    Ok(text) => object([("body", text.into()), ("base64Encoded", false.into())]),
+                    Err(_) => object([("body", base64_encode(body).into()), ("base64Encoded", true.into())]),
+                })
             }
-            _ => CdpResponse::error(cmd.id, CdpError::method_not_found(&cmd.method)),
+            _ => Err(CdpError::method_not_found(&cmd.method)),
         }
     }
-    
-    fn handle_dom(&mut self, cmd: CdpCommand) -> CdpResponse {
+
+    fn handle_dom(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
         match cmd.method.as_str() {
             "DOM.enable" => {
                 self.enable_domain("DOM");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "DOM.disable" => {
                 self.disable_domain("DOM");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "DOM.getDocument" => {
-                let root = DomNode {
-                    node_id: 1,
-                    node_type: 9,
-                    node_name: "#document".to_string(),
-                    local_name: "".to_string(),
-                    node_value: "".to_string(),
-                    child_node_count: Some(1),
-                    children: None,
-                    attributes: None,
-                };
-                
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("root".into(), root.to_value()),
-                ].into_iter().collect()))
+                let depth = cmd.param("depth").and_then(Value::as_i64).unwrap_or(1);
+                let root = self.inspector.root().ok_or_else(|| CdpError::server_error("Document is not available"))?;
+                Ok(object([("root", self.dom_node(root, depth))]))
             }
             "DOM.requestChildNodes" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                let node = self.node(cmd)?;
+                let depth = cmd.param("depth").and_then(Value::as_i64).unwrap_or(1);
+                let children = self.dom_children(node, depth);
+                let parent = node.id;
+                self.emit_domain_event(CdpEvent::new("DOM.setChildNodes", object([
+                    ("parentId", parent.into()),
+                    ("nodes", Value::Seq(children)),
+                ])));
+                Ok(object([]))
+            }
+            "DOM.describeNode" => {
+                let node = self.node(cmd)?;
+                let depth = cmd.param("depth").and_then(Value::as_i64).unwrap_or(0);
+                Ok(object([("node", self.dom_node(node, depth))]))
+            }
+            "DOM.querySelector" | "DOM.querySelectorAll" => {
+                let root = self.node(cmd)?.id;
+                let matches = self.inspector.query_selector_all(root, cmd.str_param("selector")?);
+                if cmd.method == "DOM.querySelector" {
+                    Ok(object([("nodeId", matches.first().copied().unwrap_or(0).into())]))
+                } else {
+                    Ok(object([("nodeIds", Value::Seq(matches.into_iter().map(Value::from).collect()))]))
+                }
+            }
+            "DOM.getOuterHTML" => {
+                let node = self.node(cmd)?.id;
+                let html = self.inspector.outer_html(node).unwrap_or_default();
+                Ok(object([("outerHTML", html.into())]))
+            }
+            "DOM.setAttributeValue" => {
+                let node = self.node(cmd)?.id;
+                let (name, value) = (cmd.str_param("name")?, cmd.str_param("value")?);
+                self.inspector.set_attribute(node, name, value);
+                self.emit_domain_event(CdpEvent::new("DOM.attributeModified", object([
+                    ("nodeId", node.into()),
+                    ("name", name.into()),
+                    ("value", value.into()),
+                ])));
+                Ok(object([]))
             }
-            _ => CdpResponse::error(cmd.id, CdpError::method_not_found(&cmd.method)),
+            "DOM.removeAttribute" => {
+                let node = self.node(cmd)?.id;
+                let name = cmd.str_param("name")?;
+                self.inspector.remove_attribute(node, name);
+                self.emit_domain_event(CdpEvent::new("DOM.attributeRemoved", object([
+                    ("nodeId", node.into()),
+                    ("name", name.into()),
+                ])));
+                Ok(object([]))
+            }
+            "DOM.setInspectedNode" => {
+                let node = self.node(cmd)?.id;
+                self.inspector.select(node);
+                // Becomes $0 in the console
+                self.console.set_selected_element(node);
+                Ok(object([]))
+            }
+            "DOM.setNodeValue" => {
+                let node = self.node(cmd)?.id;
+                let value = cmd.str_param("value")?;
+                if !self.inspector.set_text(node, value) {
+                    return Err(CdpError::server_error("Node is not a text or comment node"));
+                }
+                self.emit_domain_event(CdpEvent::new("DOM.characterDataModified", object([
+                    ("nodeId", node.into()),
+                    ("characterData", value.into()),
+                ])));
+                Ok(object([]))
+            }
+            "DOM.removeNode" => {
+                let node = self.node(cmd)?;
+                let (id, parent) = (node.id, node.parent);
+                if !self.inspector.remove_node(id) {
+                    return Err(CdpError::server_error("Cannot remove the document node"));
+                }
+                if let Some(parent) = parent {
+                    self.emit_domain_event(CdpEvent::new("DOM.childNodeRemoved", object([
+                        ("parentNodeId", parent.into()),
+                        ("nodeId", id.into()),
+                    ])));
+                }
+                Ok(object([]))
+            }
+            "DOM.getBoxModel" => {
+                let node = self.node(cmd)?.id;
+                let model = self.inspector.get_box_model(node)
+                    .ok_or_else(|| CdpError::server_error("Could not compute box model."))?;
+                Ok(object([("model", object([
+                    ("content", quad(&model.content)),
+                    ("padding", quad(&model.padding)),
+                    ("border", quad(&model.border)),
+                    ("margin", quad(&model.margin)),
+                    ("width", model.width.into()),
+                    ("height", model.height.into()),
+                ]))]))
+            }
+            "DOM.highlightNode" => {
+                let node = self.node(cmd)?.id;
+                self.inspector.highlight(Some(node));
+                Ok(object([]))
+            }
+            "DOM.hideHighlight" => {
+                self.inspector.highlight(None);
+                Ok(object([]))
+            }
+            _ => Err(CdpError::method_not_found(&cmd.method)),
         }
     }
-    
-    fn handle_css(&mut self, cmd: CdpCommand) -> CdpResponse {
+
+    fn handle_overlay(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
+        match cmd.method.as_str() {
+            "Overlay.enable" => {
+                self.enable_domain("Overlay");
+                Ok(object([]))
+            }
+            "Overlay.disable" => {
+                self.disable_domain("Overlay");
+                self.inspector.highlight(None);
+                Ok(object([]))
+            }
+            "Overlay.highlightNode" => {
+                let node = cmd.param("nodeId").and_then(Value::as_u64)
+                    .or_else(|| cmd.param("backendNodeId").and_then(Value::as_u64))
+                    .ok_or_else(|| CdpError::invalid_params("Invalid parameters: nodeId: integer value expected"))?;
+                if self.inspector.get_node(node).is_none() {
+                    return Err(CdpError::server_error("Could not find node with given id"));
+                }
+                self.inspector.highlight(Some(node));
+                Ok(object([]))
+            }
+            "Overlay.hideHighlight" => {
+                self.inspector.highlight(None);
+                Ok(object([]))
+            }
+            _ => Err(CdpError::method_not_found(&cmd.method)),
+        }
+    }
+
+    fn handle_css(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
         match cmd.method.as_str() {
             "CSS.enable" => {
                 self.enable_domain("CSS");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "CSS.disable" => {
                 self.disable_domain("CSS");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "CSS.getMatchedStylesForNode" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("matchedCSSRules".into(), serde_value::Value::Seq(vec![])),
-                    ("inherited".into(), serde_value::Value::Seq(vec![])),
-                ].into_iter().collect()))
+                let node = self.node(cmd)?;
+                let rules = self.inspector.get_matching_rules(node.id).iter().map(|rule| {
+                    let properties = rule.properties.iter()
+                        .map(|p| css_property(&p.name, &p.value, p.priority))
+                        .collect();
+                    let origin = if rule.source.user_agent { "user-agent" } else { "regular" };
+                    let mut entries = vec![
+                        ("selectorList", object([
+                            ("selectors", Value::Seq(rule.selector.split(',').map(|s| object([("text", s.trim().into())])).collect())),
+                            ("text", rule.selector.as_str().into()),
+                        ])),
+                        ("origin", origin.into()),
+                        ("style", object([("cssProperties", Value::Seq(properties)), ("shorthandEntries", Value::Seq(vec![]))])),
+                    ];
+                    if let Some(url) = &rule.source.url {
+                        entries.push(("sourceURL", url.as_str().into()));
+                    }
+                    let selectors = rule.selector.split(',').count() as u64;
+                    object([
+                        ("rule", object(entries)),
+                        ("matchingSelectors", Value::Seq((0..selectors).map(Value::from).collect())),
+                    ])
+                }).collect();
+                let mut result = vec![
+                    ("matchedCSSRules", Value::Seq(rules)),
+                    ("inherited", Value::Seq(vec![])),
+                ];
+                if let Some(style) = inline_style(node) {
+                    result.push(("inlineStyle", style));
+                }
+                Ok(object(result))
+            }
+            "CSS.getInlineStylesForNode" => {
+                let node = self.node(cmd)?;
+                Ok(object(inline_style(node).map(|style| ("inlineStyle", style))))
+            }
+            "CSS.forcePseudoState" => {
+                let node = self.node(cmd)?.id;
+                let states: Vec<&str> = match cmd.param("forcedPseudoClasses") {
+                    Some(Value::Seq(states)) => states.iter().filter_map(Value::as_str).collect(),
+                    _ => return Err(CdpError::invalid_params("Invalid parameters: forcedPseudoClasses: array expected")),
+                };
+                if !self.inspector.force_pseudo_state(node, &states) {
+                    return Err(CdpError::server_error("Node is not an element"));
+                }
+                Ok(object([]))
             }
             "CSS.getComputedStyleForNode" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("computedStyle".into(), serde_value::Value::Seq(vec![])),
-                ].into_iter().collect()))
+                let node = self.node(cmd)?;
+                let mut properties: Vec<(&String, &String)> = node.computed_styles.iter().collect();
+                properties.sort();
+                let properties = properties.into_iter()
+                    .map(|(name, value)| object([("name", name.as_str().into()), ("value", value.as_str().into())]))
+                    .collect();
+                Ok(object([("computedStyle", Value::Seq(properties))]))
             }
-            _ => CdpResponse::error(cmd.id, CdpError::method_not_found(&cmd.method)),
+            "CSS.startRuleUsageTracking" => {
+                self.coverage.start(CoverageType::Css);
+                Ok(object([]))
+            }
+            "CSS.takeCoverageDelta" => Ok(object([
+                ("coverage", self.rule_usage()),
+                ("timestamp", 0.0.into()),
+            ])),
+            "CSS.stopRuleUsageTracking" => {
+                let usage = self.rule_usage();
+                self.coverage.stop(CoverageType::Css);
+                Ok(object([("ruleUsage", usage)]))
+            }
+            _ => Err(CdpError::method_not_found(&cmd.method)),
         }
     }
-    
-    fn handle_page(&mut self, cmd: CdpCommand) -> CdpResponse {
+
+    fn handle_page(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
         match cmd.method.as_str() {
             "Page.enable" => {
                 self.enable_domain("Page");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "Page.disable" => {
                 self.disable_domain("Page");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "Page.getFrameTree" => {
-                let frame = PageFrame {
-                    id: "main".to_string(),
-                    url: "about:blank".to_string(),
-                    security_origin: "".to_string(),
-                    mime_type: "text/html".to_string(),
-                };
-                
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("frameTree".into(), serde_value::Value::Map(vec![
-                        ("frame".into(), frame.to_value()),
-                        ("childFrames".into(), serde_value::Value::Seq(vec![])),
-                    ].into_iter().collect())),
-                ].into_iter().collect()))
+                Ok(object([("frameTree", object([
+                    ("frame", self.frame_value()),
+                    ("childFrames", Value::Seq(vec![])),
+                ]))]))
             }
-            "Page.reload" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+            "Page.getResourceTree" => {
+                let resources = self.network.get_requests().iter()
+                    .filter_map(|request| {
+                        let response = self.network.get_response(request.id)?;
+                        let mime_type = response.content_type.as_deref().unwrap_or("").split(';').next().unwrap_or("").trim();
+                        Some(object([
+                            ("url", request.url.as_str().into()),
+                            ("type", resource_type(mime_type).into()),
+                            ("mimeType", mime_type.into()),
+                        ]))
+                    })
+                    .collect();
+                Ok(object([("frameTree", object([
+                    ("frame", self.frame_value()),
+                    ("childFrames", Value::Seq(vec![])),
+                    ("resources", Value::Seq(resources)),
+                ]))]))
             }
             "Page.navigate" => {
-                let url = cmd.params.get("url").and_then(|v| v.as_str()).unwrap_or("");
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("frameId".into(), serde_value::Value::String("main".to_string())),
-                ].into_iter().collect()))
+                let url = cmd.str_param("url")?;
+                self.navigations.push(url.to_string());
+                Ok(object([("frameId", MAIN_FRAME.into()), ("loaderId", MAIN_FRAME.into())]))
             }
-            _ => CdpResponse::error(cmd.id, CdpError::method_not_found(&cmd.method)),
+            "Page.reload" => {
+                self.navigations.push(self.page_url.clone());
+                Ok(object([]))
+            }
+            "Page.bringToFront" | "Page.setLifecycleEventsEnabled" => Ok(object([])),
+            _ => Err(CdpError::method_not_found(&cmd.method)),
         }
     }
-    
-    fn handle_console(&mut self, cmd: CdpCommand) -> CdpResponse {
+
+    fn handle_console(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
         match cmd.method.as_str() {
             "Console.enable" => {
                 self.enable_domain("Console");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                self.replay_console();
+                Ok(object([]))
             }
             "Console.disable" => {
                 self.disable_domain("Console");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "Console.clearMessages" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                self.console.clear();
+                Ok(object([]))
             }
-            _ => CdpResponse::error(cmd.id, CdpError::method_not_found(&cmd.method)),
+            _ => Err(CdpError::method_not_found(&cmd.method)),
         }
     }
-    
-    fn handle_profiler(&mut self, cmd: CdpCommand) -> CdpResponse {
+
+    fn handle_profiler(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
         match cmd.method.as_str() {
             "Profiler.enable" => {
                 self.enable_domain("Profiler");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "Profiler.disable" => {
                 self.disable_domain("Profiler");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
+            }
+            "Profiler.start" => Ok(object([])),
+            "Profiler.startPreciseCoverage" => {
+                self.coverage.start(CoverageType::Js);
+                Ok(object([("timestamp", 0.0.into())]))
             }
-            "Profiler.start" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+            "Profiler.takePreciseCoverage" => Ok(object([
+                ("result", self.script_coverage()),
+                ("timestamp", 0.0.into()),
+            ])),
+            "Profiler.stopPreciseCoverage" => {
+                self.coverage.stop(CoverageType::Js);
+                Ok(object([]))
             }
             "Profiler.stop" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("profile".into(), serde_value::Value::Map(vec![
-                        ("nodes".into(), serde_value::Value::Seq(vec![])),
-                        ("startTime".into(), serde_value::Value::F64(0.0)),
-                        ("endTime".into(), serde_value::Value::F64(0.0)),
-                    ].into_iter().collect())),
-                ].into_iter().collect()))
+                Ok(object([("profile", object([
+                    ("nodes", Value::Seq(vec![])),
+                    ("startTime", 0.0.into()),
+                    ("endTime", 0.0.into()),
+                ]))]))
+            }
+            _ => Err(CdpError::method_not_found(&cmd.method)),
+        }
+    }
+
+    fn handle_tracing(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
+        match cmd.method.as_str() {
+            "Tracing.getCategories" => Ok(object([("categories", Value::Seq(vec!["devtools.timeline".into()]))])),
+            "Tracing.start" => {
+                if self.performance.is_recording() {
+                    return Err(CdpError::server_error("Tracing is already started"));
+                }
+                self.performance.start_recording();
+                self.enable_domain("Tracing");
+                Ok(object([]))
+            }
+            "Tracing.end" => {
+                if !self.performance.is_recording() {
+                    return Err(CdpError::server_error("Tracing is not started"));
+                }
+                self.performance.stop_recording();
+                let events = self.performance.tracer().trace_events();
+                // The recording goes to the session that started it
+                self.emit_domain_event(CdpEvent::new("Tracing.dataCollected", object([("value", Value::Seq(events))])));
+                self.emit_domain_event(CdpEvent::new("Tracing.tracingComplete", object([("dataLossOccurred", false.into())])));
+                self.disable_domain("Tracing");
+                Ok(object([]))
+            }
+            _ => Err(CdpError::method_not_found(&cmd.method)),
+        }
+    }
+
+    fn handle_accessibility(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
+        match cmd.method.as_str() {
+            "Accessibility.enable" => {
+                self.enable_domain("Accessibility");
+                Ok(object([]))
+            }
+            "Accessibility.disable" => {
+                self.disable_domain("Accessibility");
+                Ok(object([]))
+            }
+            "Accessibility.getFullAXTree" => {
+                let nodes = self.accessibility.tree_order().into_iter()
+                    .filter_map(|id| self.accessibility.node(id))
+                    .map(ax_node)
+                    .collect();
+                Ok(object([("nodes", Value::Seq(nodes))]))
+            }
+            "Accessibility.getPartialAXTree" => {
+                let dom_node = cmd.param("nodeId").and_then(Value::as_u64)
+                    .or_else(|| cmd.param("backendNodeId").and_then(Value::as_u64))
+                    .ok_or_else(|| CdpError::invalid_params("Invalid parameters: nodeId: integer value expected"))?;
+                let node = self.accessibility.node_for_dom(dom_node)
+                    .ok_or_else(|| CdpError::server_error("Could not find accessibility node for the DOM node"))?;
+
+                // The node, then its ancestors and children unless only the
+                // node itself was asked for
+                let mut nodes = vec![ax_node(node)];
+                if cmd.param("fetchRelatives").and_then(Value::as_bool) != Some(false) {
+                    let mut parent = node.parent;
+                    while let Some(ancestor) = parent.and_then(|id| self.accessibility.node(id)) {
+                        nodes.push(ax_node(ancestor));
+                        parent = ancestor.parent;
+                    }
+                    nodes.extend(node.children.iter().filter_map(|id| self.accessibility.node(*id)).map(ax_node));
+                }
+                Ok(object([("nodes", Value::Seq(nodes))]))
+            }
+            "Accessibility.getChildAXNodes" => {
+                let id = cmd.str_param("id")?;
+                let node = id.parse::<u64>().ok()
+                    .and_then(|id| self.accessibility.node(id))
+                    .ok_or_else(|| CdpError::server_error("Could not find accessibility node with given id"))?;
+                let nodes = node.children.iter().filter_map(|id| self.accessibility.node(*id)).map(ax_node).collect();
+                Ok(object([("nodes", Value::Seq(nodes))]))
             }
-            _ => CdpResponse::error(cmd.id, CdpError::method_not_found(&cmd.method)),
+            _ => Err(CdpError::method_not_found(&cmd.method)),
         }
     }
-    
-    fn handle_heap_profiler(&mut self, cmd: CdpCommand) -> CdpResponse {
+
+    fn handle_heap_profiler(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
         match cmd.method.as_str() {
             "HeapProfiler.enable" => {
                 self.enable_domain("HeapProfiler");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
             "HeapProfiler.disable" => {
                 self.disable_domain("HeapProfiler");
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
-            }
-            "HeapProfiler.takeHeapSnapshot" => {
-                // Would trigger snapshot
-                CdpResponse::success(cmd.id, serde_value::Value::Unit)
+                Ok(object([]))
             }
-            _ => CdpResponse::error(cmd.id, CdpError::method_not_found(&cmd.method)),
+            // Would trigger snapshot
+            "HeapProfiler.takeHeapSnapshot" => Ok(object([])),
+            _ => Err(CdpError::method_not_found(&cmd.method)),
         }
     }
-    
-    fn handle_target(&mut self, cmd: CdpCommand) -> CdpResponse {
+
+    fn handle_target(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
         match cmd.method.as_str() {
-            "Target.getTargets" => {
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("targetInfos".into(), serde_value::Value::Seq(vec![
-                        serde_value::Value::Map(vec![
-                            ("targetId".into(), serde_value::Value::String("main".to_string())),
-                            ("type".into(), serde_value::Value::String("page".to_string())),
-                            ("title".into(), serde_value::Value::String("fOS Browser".to_string())),
-                            ("url".into(), serde_value::Value::String("about:blank".to_string())),
-                        ].into_iter().collect()),
-                    ])),
-                ].into_iter().collect()))
-            }
+            "Target.getTargets" => Ok(object([("targetInfos", Value::Seq(vec![self.target_info()]))])),
+            "Target.getTargetInfo" => Ok(object([("targetInfo", self.target_info())])),
             "Target.attachToTarget" => {
                 let session_id = self.create_session();
-                CdpResponse::success(cmd.id, serde_value::Value::Map(vec![
-                    ("sessionId".into(), serde_value::Value::String(session_id)),
-                ].into_iter().collect()))
+                self.emit_event(CdpEvent::new("Target.attachedToTarget", object([
+                    ("sessionId", session_id.as_str().into()),
+                    ("targetInfo", self.target_info()),
+                    ("waitingForDebugger", false.into()),
+                ])));
+                Ok(object([("sessionId", session_id.into())]))
+            }
+            "Target.detachFromTarget" => {
+                let session_id = cmd.str_param("sessionId")?;
+                self.close_session(session_id);
+                Ok(object([]))
             }
-            _ => CdpResponse::error(cmd.id, CdpError::method_not_found(&cmd.method)),
+            "Target.setDiscoverTargets" | "Target.setAutoAttach" => Ok(object([])),
+            _ => Err(CdpError::method_not_found(&cmd.method)),
+        }
+    }
+
+    fn handle_browser(&mut self, cmd: &CdpCommand) -> Result<Value, CdpError> {
+        match cmd.method.as_str() {
+            "Browser.getVersion" => Ok(object([
+                ("protocolVersion", PROTOCOL_VERSION.into()),
+                ("product", PRODUCT.into()),
+                ("revision", env!("CARGO_PKG_VERSION").into()),
+                ("userAgent", PRODUCT.into()),
+                ("jsVersion", "".into()),
+            ])),
+            _ => Err(CdpError::method_not_found(&cmd.method)),
+        }
+    }
+
+    // === Helpers ===
+
+    /// Node named by the command's `nodeId`
+    fn node(&self, cmd: &CdpCommand) -> Result<&InspectedNode, CdpError> {
+        let id = cmd.u64_param("nodeId")?;
+        self.inspector.get_node(id).ok_or_else(|| CdpError::server_error("Could not find node with given id"))
+    }
+
+    /// CDP `Node`, with children down to `depth` levels (-1 for all)
+    fn dom_node(&self, node: &InspectedNode, depth: i64) -> Value {
+        let text = node.text_content.clone().unwrap_or_default();
+        let (node_type, name, local_name, value) = match node.node_type {
+            NodeType::Element => (1u64, node.tag_name.to_ascii_uppercase(), node.tag_name.to_ascii_lowercase(), String::new()),
+            NodeType::Text => (3, "#text".to_string(), String::new(), text),
+            NodeType::Comment => (8, "#comment".to_string(), String::new(), text),
+            NodeType::Document => (9, "#document".to_string(), String::new(), String::new()),
+            NodeType::DocumentType => (10, "html".to_string(), String::new(), String::new()),
+        };
+        let mut entries = vec![
+            ("nodeId", Value::from(node.id)),
+            ("backendNodeId", node.id.into()),
+            ("nodeType", node_type.into()),
+            ("nodeName", name.into()),
+            ("localName", local_name.into()),
+            ("nodeValue", value.into()),
+            ("childNodeCount", (node.children.len() as u64).into()),
+        ];
+        if let Some(parent) = node.parent {
+            entries.push(("parentId", parent.into()));
+        }
+        match node.node_type {
+            NodeType::Element => {
+                let attributes = node.attribute_list().into_iter().flat_map(|(n, v)| [Value::from(n), Value::from(v)]).collect();
+                entries.push(("attributes", Value::Seq(attributes)));
+            }
+            NodeType::Document => {
+                entries.push(("documentURL", self.page_url.as_str().into()));
+                entries.push(("baseURL", self.page_url.as_str().into()));
+            }
+            NodeType::DocumentType => {
+                entries.push(("publicId", "".into()));
+                entries.push(("systemId", "".into()));
+            }
+            _ => {}
+        }
+        if depth != 0 && !node.children.is_empty() {
+            entries.push(("children", Value::Seq(self.dom_children(node, depth))));
+        }
+        object(entries)
+    }
+
+    fn dom_children(&self, node: &InspectedNode, depth: i64) -> Vec<Value> {
+        node.children.iter()
+            .filter_map(|id| self.inspector.get_node(*id))
+            .map(|child| self.dom_node(child, if depth < 0 { depth } else { depth - 1 }))
+            .collect()
+    }
+
+    fn frame_value(&self) -> Value {
+        object([
+            ("id", MAIN_FRAME.into()),
+            ("loaderId", MAIN_FRAME.into()),
+            ("url", self.page_url.as_str().into()),
+            ("securityOrigin", origin(&self.page_url).into()),
+            ("mimeType", "text/html".into()),
+        ])
+    }
+
+    fn target_info(&self) -> Value {
+        object([
+            ("targetId", MAIN_FRAME.into()),
+            ("type", "page".into()),
+            ("title", self.page_title.as_str().into()),
+            ("url", self.page_url.as_str().into()),
+            ("attached", (!self.sessions.is_empty()).into()),
+        ])
+    }
+
+    fn execution_context(&self) -> Value {
+        object([
+            ("id", MAIN_CONTEXT.into()),
+            ("origin", origin(&self.page_url).into()),
+            ("name", "".into()),
+            ("uniqueId", format!("{}-{}", MAIN_FRAME, MAIN_CONTEXT).into()),
+            ("auxData", object([("isDefault", true.into()), ("type", "default".into()), ("frameId", MAIN_FRAME.into())])),
+        ])
+    }
+
+    /// Report the messages still in the console's buffer again, as a newly
+    /// enabled domain expects
+    fn replay_console(&mut self) {
+        let held = self.console.get_messages().len() as u64;
+        self.console_reported = self.console.logged_count().saturating_sub(held);
+        self.collect_events();
+    }
+
+    fn script(&self, script_id: &str) -> Result<&SourceFile, CdpError> {
+        script_id.parse::<usize>().ok()
+            .and_then(|i| self.scripts.get(i))
+            .ok_or_else(|| CdpError::server_error("No script for id"))
+    }
+
+    /// Recorded CSS rules as `CSS.RuleUsage` objects
+    fn rule_usage(&self) -> Value {
+        let rules = self.coverage.entries().iter()
+            .filter(|e| e.kind == CoverageType::Css)
+            .flat_map(|e| e.ranges.iter().map(move |r| object([
+                ("styleSheetId", e.url.as_str().into()),
+                ("startOffset", (r.start as f64).into()),
+                ("endOffset", (r.end as f64).into()),
+                ("used", (r.count > 0).into()),
+            ])))
+            .collect();
+        Value::Seq(rules)
+    }
+
+    /// Recorded scripts as `Profiler.ScriptCoverage` objects
+    fn script_coverage(&self) -> Value {
+        let scripts = self.coverage.entries().iter()
+            .filter(|e| e.kind == CoverageType::Js)
+            .map(|e| {
+                let functions = e.ranges.iter().map(|r| object([
+                    ("functionName", r.name.as_str().into()),
+                    ("ranges", Value::Seq(vec![object([
+                        ("startOffset", (r.start as u64).into()),
+                        ("endOffset", (r.end as u64).into()),
+                        ("count", r.count.into()),
+                    ])])),
+                    ("isBlockCoverage", false.into()),
+                ])).collect();
+                object([
+                    ("scriptId", self.script_id(&e.url).into()),
+                    ("url", e.url.as_str().into()),
+                    ("functions", Value::Seq(functions)),
+                ])
+            })
+            .collect();
+        Value::Seq(scripts)
+    }
+
+    /// Script ID for a URL, empty if the script is unknown
+    fn script_id(&self, url: &str) -> String {
+        self.scripts.iter().position(|s| s.url == url).map(|i| i.to_string()).unwrap_or_default()
+    }
+
+    fn script_parsed(&self, index: usize) -> CdpEvent {
+        let script = &self.scripts[index];
+        let lines = script.content.lines().count().max(1) as u64;
+        let last_line = script.content.lines().last().unwrap_or("");
+        let mut params = vec![
+            ("scriptId", Value::from(index.to_string())),
+            ("url", script.url.as_str().into()),
+            ("startLine", 0u64.into()),
+            ("startColumn", 0u64.into()),
+            ("endLine", (lines - 1).into()),
+            ("endColumn", (last_line.chars().count() as u64).into()),
+            ("executionContextId", MAIN_CONTEXT.into()),
+            ("hash", "".into()),
+            ("length", (script.content.len() as u64).into()),
+        ];
+        if let Some(map) = &script.source_map_url {
+            params.push(("sourceMapURL", map.as_str().into()));
+        }
+        CdpEvent::new("Debugger.scriptParsed", object(params))
+    }
+
+    fn paused_event(&self) -> CdpEvent {
+        let stack = self.debugger.get_call_stack();
+        let frames = stack.iter().map(|frame| self.call_frame(frame)).collect();
+        let mut event = CdpEvent::debugger_paused("other", frames);
+        let hit: Vec<Value> = stack.first()
+            .map(|top| self.debugger.get_breakpoints().into_iter()
+                .filter(|bp| bp.enabled && bp.url == top.url && bp.line == top.line)
+                .map(|bp| Value::from(bp.id.to_string()))
+                .collect())
+            .unwrap_or_default();
+        if let Value::Map(params) = &mut event.params {
+            if !hit.is_empty() {
+                params.insert("reason".into(), "breakpoint".into());
+            }
+            params.insert("hitBreakpoints".into(), Value::Seq(hit));
+        }
+        event
+    }
+
+    fn call_frame(&self, frame: &CallFrame) -> Value {
+        let script_id = self.script_id(&frame.url);
+        let scopes = frame.scope_chain.iter().enumerate().map(|(i, scope)| {
+            let kind = match scope.scope_type {
+                ScopeType::Global => "global",
+                ScopeType::Local => "local",
+                ScopeType::Closure => "closure",
+                ScopeType::Block => "block",
+                ScopeType::With => "with",
+                ScopeType::Catch => "catch",
+            };
+            let mut entries = vec![
+                ("type", Value::from(kind)),
+                ("object", object([
+                    ("type", "object".into()),
+                    ("className", "Object".into()),
+                    ("description", "Object".into()),
+                    ("objectId", format!("scope-{}-{}", frame.id, i).into()),
+                ])),
+            ];
+            if let Some(name) = &scope.name {
+                entries.push(("name", name.as_str().into()));
+            }
+            object(entries)
+        }).collect();
+        let this = match &frame.this_value {
+            Some(description) => object([("type", "object".into()), ("className", "Object".into()), ("description", description.as_str().into())]),
+            None => object([("type", "undefined".into())]),
+        };
+        object([
+            ("callFrameId", frame.id.to_string().into()),
+            ("functionName", frame.function_name.as_str().into()),
+            ("location", location(&script_id, frame.line, frame.column)),
+            ("url", frame.url.as_str().into()),
+            ("scopeChain", Value::Seq(scopes)),
+            ("this", this),
+        ])
+    }
+
+    /// `Runtime.evaluate` result for an expression evaluated by the debugger
+    fn evaluate(&mut self, expression: &str, frame: Option<u64>) -> Value {
+        match self.debugger.evaluate(expression, frame) {
+            Ok(value) => object([("result", variable_object(&value))]),
+            Err(message) => object([
+                ("result", object([("type", "object".into()), ("subtype", "error".into()), ("description", message.as_str().into())])),
+                ("exceptionDetails", object([
+                    ("exceptionId", 1u64.into()),
+                    ("text", message.as_str().into()),
+                    ("lineNumber", 0u64.into()),
+                    ("columnNumber", 0u64.into()),
+                ])),
+            ]),
+        }
+    }
+
+    /// CDP `RemoteObject` for a console value; objects and arrays get an ID
+    /// their properties can be fetched with
+    fn remote_object(&mut self, value: &ConsoleValue) -> Value {
+        let mut entries: Vec<(&str, Value)> = match value {
+            ConsoleValue::Undefined => vec![("type", "undefined".into())],
+            ConsoleValue::Null => vec![("type", "object".into()), ("subtype", "null".into()), ("value", Value::Unit)],
+            ConsoleValue::Boolean(b) => vec![("type", "boolean".into()), ("value", (*b).into())],
+            ConsoleValue::Number(n) => vec![("type", "number".into()), ("value", (*n).into()), ("description", value.to_string().into())],
+            ConsoleValue::String(s) => vec![("type", "string".into()), ("value", s.as_str().into())],
+            ConsoleValue::Object(_) => vec![("type", "object".into()), ("className", "Object".into()), ("description", "Object".into())],
+            ConsoleValue::Array(items) => vec![
+                ("type", "object".into()),
+                ("subtype", "array".into()),
+                ("className", "Array".into()),
+                ("description", format!("Array({})", items.len()).into()),
+            ],
+            ConsoleValue::Function(_) => vec![("type", "function".into()), ("className", "Function".into()), ("description", value.to_string().into())],
+            ConsoleValue::Symbol(_) => vec![("type", "symbol".into()), ("description", value.to_string().into())],
+            ConsoleValue::Error { name, .. } => vec![
+                ("type", "object".into()),
+                ("subtype", "error".into()),
+                ("className", name.as_str().into()),
+                ("description", value.to_string().into()),
+            ],
+        };
+        if matches!(value, ConsoleValue::Object(_) | ConsoleValue::Array(_)) {
+            let id = format!("object-{}", self.next_object_id);
+            self.next_object_id += 1;
+            self.remote_objects.insert(id.clone(), value.clone());
+            entries.push(("objectId", id.into()));
+        }
+        object(entries)
+    }
+
+    /// Property descriptors of a remote object or scope
+    fn properties(&mut self, object_id: &str) -> Option<Vec<Value>> {
+        let descriptor = |name: &str, value: Value| object([
+            ("name", name.into()),
+            ("value", value),
+            ("writable", true.into()),
+            ("configurable", true.into()),
+            ("enumerable", true.into()),
+            ("isOwn", true.into()),
+        ]);
+        if let Some(scope) = object_id.strip_prefix("scope-") {
+            let (frame, index) = scope.split_once('-')?;
+            let (frame, index): (u64, usize) = (frame.parse().ok()?, index.parse().ok()?);
+            let frame = self.debugger.get_call_stack().iter().find(|f| f.id == frame)?;
+            let mut variables: Vec<&VariableValue> = frame.scope_chain.get(index)?.variables.values().collect();
+            variables.sort_by(|a, b| a.name.cmp(&b.name));
+            return Some(variables.into_iter().map(|v| descriptor(&v.name, variable_object(v))).collect());
+        }
+        let properties: Vec<(String, ConsoleValue)> = match self.remote_objects.get(object_id)? {
+            ConsoleValue::Object(props) => props.iter().map(|(k, v)| (k.clone(), (**v).clone())).collect(),
+            ConsoleValue::Array(items) => items.iter().enumerate().map(|(i, v)| (i.to_string(), v.clone())).collect(),
+            _ => Vec::new(),
+        };
+        Some(properties.iter().map(|(name, value)| {
+            let value = self.remote_object(value);
+            descriptor(name, value)
+        }).collect())
+    }
+}
+
+/// Protocol version advertised to clients
+pub const PROTOCOL_VERSION: &str = "1.3";
+
+/// Product name advertised to clients
+pub const PRODUCT: &str = concat!("fOS/", env!("CARGO_PKG_VERSION"));
+
+fn location(script_id: &str, line: u32, column: u32) -> Value {
+    object([
+        ("scriptId", script_id.into()),
+        ("lineNumber", (line as u64).into()),
+        ("columnNumber", (column as u64).into()),
+    ])
+}
+
+/// `RemoteObject` for a debugger variable, whose value is a display string
+fn variable_object(variable: &VariableValue) -> Value {
+    let description = variable.value.as_str();
+    let value = match variable.value_type.as_str() {
+        "number" => description.parse::<f64>().ok().map(Value::from),
+        "boolean" => description.parse::<bool>().ok().map(Value::from),
+        "string" => Some(description.trim_matches('"').into()),
+        _ => None,
+    };
+    let mut entries = vec![("type", Value::from(variable.value_type.as_str())), ("description", description.into())];
+    if let Some(value) = value {
+        entries.push(("value", value));
+    }
+    object(entries)
+}
+
+/// CDP `Quad`: the corners of a box clockwise from the top left
+fn quad(rect: &crate::elements::Rect) -> Value {
+    let points = [rect.left, rect.top, rect.right, rect.top, rect.right, rect.bottom, rect.left, rect.bottom];
+    Value::Seq(points.into_iter().map(Value::from).collect())
+}
+
+fn css_property(name: &str, value: &str, important: bool) -> Value {
+    object([
+        ("name", name.into()),
+        ("value", value.into()),
+        ("important", important.into()),
+    ])
+}
+
+/// Declarations of a node's `style` attribute
+fn inline_style(node: &InspectedNode) -> Option<Value> {
+    let style = node.attributes.get("style")?;
+    let properties = style.split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .map(|(name, value)| {
+            let value = value.trim();
+            let (value, important) = match value.strip_suffix("!important") {
+                Some(v) => (v.trim(), true),
+                None => (value, false),
+            };
+            css_property(name.trim(), value, important)
+        })
+        .collect();
+    Some(object([
+        ("cssProperties", Value::Seq(properties)),
+        ("shorthandEntries", Value::Seq(vec![])),
+        ("cssText", style.as_str().into()),
+    ]))
+}
+
+/// CDP `AXNode`; states become boolean properties, "collapsed" being
+/// `expanded: false`
+fn ax_node(node: &AxNode) -> Value {
+    let ax_value = |kind: &str, value: Value| object([("type", kind.into()), ("value", value)]);
+    let properties = node.states.iter().map(|state| {
+        let (name, on) = match state.as_str() {
+            "collapsed" => ("expanded", false),
+            state => (state, true),
+        };
+        object([("name", name.into()), ("value", ax_value("booleanOrUndefined", on.into()))])
+    }).collect();
+    let mut entries = vec![
+        ("nodeId", Value::from(node.id.to_string())),
+        ("ignored", node.ignored.into()),
+        ("role", ax_value("role", node.role.as_str().into())),
+        ("name", ax_value("computedString", node.name.as_str().into())),
+        ("description", ax_value("computedString", node.description.as_str().into())),
+        ("properties", Value::Seq(properties)),
+        ("childIds", Value::Seq(node.children.iter().map(|id| id.to_string().into()).collect())),
+    ];
+    if let Some(value) = &node.value {
+        entries.push(("value", ax_value("string", value.as_str().into())));
+    }
+    if let Some(parent) = node.parent {
+        entries.push(("parentId", parent.to_string().into()));
+    }
+    if let Some(dom_node) = node.dom_node {
+        entries.push(("backendDOMNodeId", dom_node.into()));
+    }
+    object(entries)
+}
+
+fn network_request_event(request: &crate::NetworkRequest, document_url: &str) -> CdpEvent {
+    let mut event = CdpEvent::network_request_will_be_sent(&request.id.to_string(), &request.url, &request.method);
+    if let Value::Map(params) = &mut event.params {
+        let timestamp = request.timing.start_time as f64 / 1000.0;
+        params.insert("timestamp".into(), timestamp.into());
+        params.insert("wallTime".into(), timestamp.into());
+        params.insert("loaderId".into(), MAIN_FRAME.into());
+        params.insert("frameId".into(), MAIN_FRAME.into());
+        params.insert("documentURL".into(), document_url.into());
+        params.insert("initiator".into(), object([("type", "other".into())]));
+        if let Some(Value::Map(req)) = params.get_mut("request") {
+            req.insert("headers".into(), object(request.request_headers.iter().map(|(k, v)| (k.as_str(), Value::from(v.as_str())))));
         }
     }
+    event
+}
+
+fn loading_failed(request_id: Value, timestamp: f64, error: &str, canceled: bool) -> CdpEvent {
+    CdpEvent::new("Network.loadingFailed", object([
+        ("requestId", request_id),
+        ("timestamp", timestamp.into()),
+        ("type", "Other".into()),
+        ("errorText", error.into()),
+        ("canceled", canceled.into()),
+    ]))
+}
+
+/// CDP resource type for a MIME type
+fn resource_type(mime_type: &str) -> &'static str {
+    match mime_type {
+        "text/html" => "Document",
+        "text/css" => "Stylesheet",
+        "application/javascript" | "text/javascript" => "Script",
+        "application/json" => "Fetch",
+        m if m.starts_with("image/") => "Image",
+        m if m.starts_with("font/") || m.contains("font") => "Font",
+        m if m.starts_with("audio/") || m.starts_with("video/") => "Media",
+        _ => "Other",
+    }
+}
+
+fn cookie_value(cookie: &Cookie) -> Value {
+    let same_site = match cookie.same_site {
+        SameSite::Strict => "Strict",
+        SameSite::Lax => "Lax",
+        SameSite::None => "None",
+    };
+    let expires = cookie.expires.map_or(-1.0, |e| e as f64);
+    object([
+        ("name", cookie.name.as_str().into()),
+        ("value", cookie.value.as_str().into()),
+        ("domain", cookie.domain.as_str().into()),
+        ("path", cookie.path.as_str().into()),
+        ("expires", expires.into()),
+        ("size", (cookie.size as u64).into()),
+        ("httpOnly", cookie.http_only.into()),
+        ("secure", cookie.secure.into()),
+        ("session", cookie.expires.is_none().into()),
+        ("sameSite", same_site.into()),
+    ])
+}
+
+/// Host part of a URL
+fn url_host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    host.split(':').next().unwrap_or("").to_ascii_lowercase()
+}
+
+/// Whether a cookie for `domain` is sent to `host`
+fn domain_matches(host: &str, domain: &str) -> bool {
+    let domain = domain.trim_start_matches('.');
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// Origin of a URL, empty for URLs without one
+fn origin(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) if scheme == "http" || scheme == "https" => {
+            let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+            format!("{}://{}", scheme, authority)
+        }
+        _ => String::new(),
+    }
 }
 
 /// CDP session
@@ -400,7 +1564,7 @@ impl CdpSession {
     pub fn new(id: String) -> Self {
         Self {
             id,
-            target_id: "main".to_string(),
+            target_id: MAIN_FRAME.to_string(),
         }
     }
 }
@@ -410,7 +1574,7 @@ impl CdpSession {
 pub struct CdpCommand {
     pub id: u64,
     pub method: String,
-    pub params: HashMap<String, serde_value::Value>,
+    pub params: HashMap<String, Value>,
     pub session_id: Option<String>,
 }
 
@@ -423,37 +1587,97 @@ impl CdpCommand {
             session_id: None,
         }
     }
-    
-    pub fn with_param(mut self, key: &str, value: serde_value::Value) -> Self {
+
+    pub fn with_param(mut self, key: &str, value: Value) -> Self {
         self.params.insert(key.to_string(), value);
         self
     }
+
+    /// Parse a command message: `{"id", "method", "params"?, "sessionId"?}`
+    pub fn from_json(text: &str) -> Result<Self, CdpError> {
+        let message = Value::from_json(text).ok_or_else(CdpError::parse_error)?;
+        let id = message.get("id").and_then(Value::as_u64)
+            .ok_or_else(|| CdpError::invalid_request("Message must have integer 'id' property"))?;
+        let method = message.get("method").and_then(Value::as_str)
+            .ok_or_else(|| CdpError::invalid_request("Message must have string 'method' property"))?;
+        let params = match message.get("params") {
+            Some(Value::Map(params)) => params.clone(),
+            None | Some(Value::Unit) => HashMap::new(),
+            Some(_) => return Err(CdpError::invalid_request("Message has property 'params' which is not an object")),
+        };
+        Ok(Self {
+            id,
+            method: method.to_string(),
+            params,
+            session_id: message.get("sessionId").and_then(Value::as_str).map(String::from),
+        })
+    }
+
+    pub fn param(&self, key: &str) -> Option<&Value> {
+        self.params.get(key)
+    }
+
+    fn str_param(&self, key: &str) -> Result<&str, CdpError> {
+        self.param(key).and_then(Value::as_str)
+            .ok_or_else(|| CdpError::invalid_params(&format!("Invalid parameters: {}: string value expected", key)))
+    }
+
+    fn u64_param(&self, key: &str) -> Result<u64, CdpError> {
+        self.param(key).and_then(Value::as_u64)
+            .ok_or_else(|| CdpError::invalid_params(&format!("Invalid parameters: {}: integer value expected", key)))
+    }
 }
 
 /// CDP response
 #[derive(Debug, Clone)]
 pub struct CdpResponse {
     pub id: u64,
-    pub result: Option<serde_value::Value>,
+    pub result: Option<Value>,
     pub error: Option<CdpError>,
+    pub session_id: Option<String>,
 }
 
 impl CdpResponse {
-    pub fn success(id: u64, result: serde_value::Value) -> Self {
+    pub fn success(id: u64, result: Value) -> Self {
         Self {
             id,
             result: Some(result),
             error: None,
+            session_id: None,
         }
     }
-    
+
     pub fn error(id: u64, error: CdpError) -> Self {
         Self {
             id,
             result: None,
             error: Some(error),
+            session_id: None,
         }
     }
+
+    /// Serialize as a protocol message
+    pub fn to_json(&self) -> String {
+        let mut entries = vec![("id", Value::from(self.id))];
+        match &self.error {
+            Some(error) => {
+                let mut fields = vec![("code", Value::I64(error.code as i64)), ("message", error.message.as_str().into())];
+                if let Some(data) = &error.data {
+                    fields.push(("data", data.as_str().into()));
+                }
+                entries.push(("error", object(fields)));
+            }
+            // Results are always objects
+            None => entries.push(("result", match &self.result {
+                None | Some(Value::Unit) => object([]),
+                Some(result) => result.clone(),
+            })),
+        }
+        if let Some(session) = &self.session_id {
+            entries.push(("sessionId", session.as_str().into()));
+        }
+        object(entries).to_json()
+    }
 }
 
 /// CDP error
@@ -465,6 +1689,22 @@ pub struct CdpError {
 }
 
 impl CdpError {
+    pub fn parse_error() -> Self {
+        Self {
+            code: -32700,
+            message: "Message must be a valid JSON".to_string(),
+            data: None,
+        }
+    }
+
+    pub fn invalid_request(message: &str) -> Self {
+        Self {
+            code: -32600,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
     pub fn method_not_found(method: &str) -> Self {
         Self {
             code: -32601,
@@ -472,7 +1712,7 @@ impl CdpError {
             data: None,
         }
     }
-    
+
     pub fn invalid_params(message: &str) -> Self {
         Self {
             code: -32602,
@@ -480,7 +1720,7 @@ impl CdpError {
             data: None,
         }
     }
-    
+
     pub fn internal_error(message: &str) -> Self {
         Self {
             code: -32603,
@@ -488,158 +1728,110 @@ impl CdpError {
             data: None,
         }
     }
+
+    /// Domain-level failure of a valid command
+    pub fn server_error(message: &str) -> Self {
+        Self {
+            code: -32000,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    pub fn session_not_found(session_id: &str) -> Self {
+        Self {
+            code: -32001,
+            message: format!("Session with given id not found: {}", session_id),
+            data: None,
+        }
+    }
 }
 
 /// CDP event
 #[derive(Debug, Clone)]
 pub struct CdpEvent {
     pub method: String,
-    pub params: serde_value::Value,
+    pub params: Value,
     pub session_id: Option<String>,
 }
 
 impl CdpEvent {
-    pub fn new(method: &str, params: serde_value::Value) -> Self {
+    pub fn new(method: &str, params: Value) -> Self {
         Self {
             method: method.to_string(),
             params,
             session_id: None,
         }
     }
-    
-    // Common events
-    
-    pub fn console_message_added(level: &str, text: &str, url: Option<&str>, line: Option<u32>) -> Self {
-        Self::new("Console.messageAdded", serde_value::Value::Map(vec![
-            ("message".into(), serde_value::Value::Map(vec![
-                ("level".into(), serde_value::Value::String(level.to_string())),
-                ("text".into(), serde_value::Value::String(text.to_string())),
-                ("url".into(), url.map(|u| serde_value::Value::String(u.to_string())).unwrap_or(serde_value::Value::Unit)),
-                ("line".into(), line.map(|l| serde_value::Value::U32(l)).unwrap_or(serde_value::Value::Unit)),
-            ].into_iter().collect())),
-        ].into_iter().collect()))
-    }
-    
-    pub fn debugger_paused(reason: &str, call_frames: Vec<serde_value::Value>) -> Self {
-        Self::new("Debugger.paused", serde_value::Value::Map(vec![
-            ("reason".into(), serde_value::Value::String(reason.to_string())),
-            ("callFrames".into(), serde_value::Value::Seq(call_frames)),
-        ].into_iter().collect()))
-    }
-    
-    pub fn debugger_resumed() -> Self {
-        Self::new("Debugger.resumed", serde_value::Value::Unit)
-    }
-    
-    pub fn network_request_will_be_sent(request_id: &str, url: &str, method: &str) -> Self {
-        Self::new("Network.requestWillBeSent", serde_value::Value::Map(vec![
-            ("requestId".into(), serde_value::Value::String(request_id.to_string())),
-            ("request".into(), serde_value::Value::Map(vec![
-                ("url".into(), serde_value::Value::String(url.to_string())),
-                ("method".into(), serde_value::Value::String(method.to_string())),
-            ].into_iter().collect())),
-            ("timestamp".into(), serde_value::Value::F64(current_time())),
-        ].into_iter().collect()))
-    }
-    
-    pub fn network_response_received(request_id: &str, url: &str, status: u16) -> Self {
-        Self::new("Network.responseReceived", serde_value::Value::Map(vec![
-            ("requestId".into(), serde_value::Value::String(request_id.to_string())),
-            ("response".into(), serde_value::Value::Map(vec![
-                ("url".into(), serde_value::Value::String(url.to_string())),
-                ("status".into(), serde_value::Value::U16(status)),
-            ].into_iter().collect())),
-            ("timestamp".into(), serde_value::Value::F64(current_time())),
-        ].into_iter().collect()))
-    }
-}
 
-// === Helper types for CDP ===
+    /// Serialize as a protocol message
+    pub fn to_json(&self) -> String {
+        let params = match &self.params {
+            Value::Unit => object([]),
+            params => params.clone(),
+        };
+        let mut entries = vec![("method", Value::from(self.method.as_str())), ("params", params)];
+        if let Some(session) = &self.session_id {
+            entries.push(("sessionId", session.as_str().into()));
+        }
+        object(entries).to_json()
+    }
 
-/// Runtime remote object
-#[derive(Debug)]
-struct RuntimeRemoteObject {
-    object_type: String,
-    value: Option<serde_value::Value>,
-    description: Option<String>,
-    object_id: Option<String>,
-}
+    // Common events
 
-impl RuntimeRemoteObject {
-    fn to_value(&self) -> serde_value::Value {
-        let mut map = vec![
-            ("type".into(), serde_value::Value::String(self.object_type.clone())),
-        ];
-        
-        if let Some(ref v) = self.value {
-            map.push(("value".into(), v.clone()));
-        }
-        if let Some(ref d) = self.description {
-            map.push(("description".into(), serde_value::Value::String(d.clone())));
-        }
-        if let Some(ref id) = self.object_id {
-            map.push(("objectId".into(), serde_value::Value::String(id.clone())));
-        }
-        
-        serde_value::Value::Map(map.into_iter().collect())
+    pub fn console_message_added(level: &str, text: &str, url: Option<&str>, line: Option<u32>) -> Self {
+        Self::new("Console.messageAdded", object([
+            ("message", object([
+                ("source", "console-api".into()),
+                ("level", level.into()),
+                ("text", text.into()),
+                ("url", url.map(Value::from).unwrap_or(Value::Unit)),
+                ("line", line.map(Value::U32).unwrap_or(Value::Unit)),
+            ])),
+        ]))
     }
-}
 
-/// DOM node for CDP
-#[derive(Debug)]
-struct DomNode {
-    node_id: u64,
-    node_type: u32,
-    node_name: String,
-    local_name: String,
-    node_value: String,
-    child_node_count: Option<u32>,
-    children: Option<Vec<DomNode>>,
-    attributes: Option<Vec<String>>,
-}
+    pub fn debugger_paused(reason: &str, call_frames: Vec<Value>) -> Self {
+        Self::new("Debugger.paused", object([
+            ("reason", reason.into()),
+            ("callFrames", Value::Seq(call_frames)),
+        ]))
+    }
 
-impl DomNode {
-    fn to_value(&self) -> serde_value::Value {
-        let mut map = vec![
-            ("nodeId".into(), serde_value::Value::U64(self.node_id)),
-            ("nodeType".into(), serde_value::Value::U32(self.node_type)),
-            ("nodeName".into(), serde_value::Value::String(self.node_name.clone())),
-            ("localName".into(), serde_value::Value::String(self.local_name.clone())),
-            ("nodeValue".into(), serde_value::Value::String(self.node_value.clone())),
-        ];
-        
-        if let Some(count) = self.child_node_count {
-            map.push(("childNodeCount".into(), serde_value::Value::U32(count)));
-        }
-        
-        serde_value::Value::Map(map.into_iter().collect())
+    pub fn debugger_resumed() -> Self {
+        Self::new("Debugger.resumed", Value::Unit)
     }
-}
 
-/// Page frame for CDP
-#[derive(Debug)]
-struct PageFrame {
-    id: String,
-    url: String,
-    security_origin: String,
-    mime_type: String,
-}
+    pub fn network_request_will_be_sent(request_id: &str, url: &str, method: &str) -> Self {
+        Self::new("Network.requestWillBeSent", object([
+            ("requestId", request_id.into()),
+            ("request", object([
+                ("url", url.into()),
+                ("method", method.into()),
+            ])),
+            ("timestamp", current_time().into()),
+        ]))
+    }
 
-impl PageFrame {
-    fn to_value(&self) -> serde_value::Value {
-        serde_value::Value::Map(vec![
-            ("id".into(), serde_value::Value::String(self.id.clone())),
-            ("url".into(), serde_value::Value::String(self.url.clone())),
-            ("securityOrigin".into(), serde_value::Value::String(self.security_origin.clone())),
-            ("mimeType".into(), serde_value::Value::String(self.mime_type.clone())),
-        ].into_iter().collect())
+    pub fn network_response_received(request_id: &str, url: &str, status: u16) -> Self {
+        Self::new("Network.responseReceived", object([
+            ("requestId", request_id.into()),
+            ("response", object([
+                ("url", url.into()),
+                ("status", Value::U16(status)),
+            ])),
+            ("timestamp", current_time().into()),
+        ]))
     }
 }
 
 /// Simple value type for CDP (standalone, no serde dependency)
 pub mod serde_value {
     use std::collections::HashMap;
-    
+
+    /// Deepest nesting accepted from clients
+    const MAX_DEPTH: usize = 128;
+
     #[derive(Debug, Clone)]
     pub enum Value {
         Unit,
@@ -658,7 +1850,12 @@ pub mod serde_value {
         Seq(Vec<Value>),
         Map(HashMap<String, Value>),
     }
-    
+
+    /// Map value from key/value pairs
+    pub fn object<'a>(entries: impl IntoIterator<Item = (&'a str, Value)>) -> Value {
+        Value::Map(entries.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
     impl Value {
         pub fn as_str(&self) -> Option<&str> {
             match self {
@@ -666,36 +1863,291 @@ pub mod serde_value {
                 _ => None,
             }
         }
-        
+
         pub fn as_u64(&self) -> Option<u64> {
             match self {
                 Value::U64(n) => Some(*n),
                 Value::U32(n) => Some(*n as u64),
                 Value::U16(n) => Some(*n as u64),
                 Value::U8(n) => Some(*n as u64),
+                _ => self.as_i64().and_then(|n| u64::try_from(n).ok()),
+            }
+        }
+
+        pub fn as_i64(&self) -> Option<i64> {
+            match self {
+                Value::I64(n) => Some(*n),
+                Value::I32(n) => Some(*n as i64),
+                Value::I16(n) => Some(*n as i64),
+                Value::I8(n) => Some(*n as i64),
+                Value::U64(n) => i64::try_from(*n).ok(),
+                Value::U32(n) => Some(*n as i64),
+                Value::U16(n) => Some(*n as i64),
+                Value::U8(n) => Some(*n as i64),
                 _ => None,
             }
         }
-        
+
+        pub fn as_f64(&self) -> Option<f64> {
+            match self {
+                Value::F64(n) => Some(*n),
+                Value::F32(n) => Some(*n as f64),
+                Value::U64(n) => Some(*n as f64),
+                _ => self.as_i64().map(|n| n as f64),
+            }
+        }
+
         pub fn as_bool(&self) -> Option<bool> {
             match self {
                 Value::Bool(b) => Some(*b),
                 _ => None,
             }
         }
+
+        /// Member of a map
+        pub fn get(&self, key: &str) -> Option<&Value> {
+            match self {
+                Value::Map(map) => map.get(key),
+                _ => None,
+            }
+        }
+
+        /// Serialize as JSON; `Unit` and non-finite numbers become `null`
+        pub fn to_json(&self) -> String {
+            let mut out = String::new();
+            self.write_json(&mut out);
+            out
+        }
+
+        fn write_json(&self, out: &mut String) {
+            match self {
+                Value::Unit => out.push_str("null"),
+                Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+                Value::I8(n) => out.push_str(&n.to_string()),
+                Value::I16(n) => out.push_str(&n.to_string()),
+                Value::I32(n) => out.push_str(&n.to_string()),
+                Value::I64(n) => out.push_str(&n.to_string()),
+                Value::U8(n) => out.push_str(&n.to_string()),
+                Value::U16(n) => out.push_str(&n.to_string()),
+                Value::U32(n) => out.push_str(&n.to_string()),
+                Value::U64(n) => out.push_str(&n.to_string()),
+                Value::F32(n) => Value::F64(*n as f64).write_json(out),
+                Value::F64(n) if n.is_finite() => out.push_str(&n.to_string()),
+                Value::F64(_) => out.push_str("null"),
+                Value::String(s) => write_string(s, out),
+                Value::Seq(items) => {
+                    out.push('[');
+                    for (i, item) in items.iter().enumerate() {
+                        if i > 0 { out.push(','); }
+                        item.write_json(out);
+                    }
+                    out.push(']');
+                }
+                Value::Map(map) => {
+                    out.push('{');
+                    for (i, (key, value)) in map.iter().enumerate() {
+                        if i > 0 { out.push(','); }
+                        write_string(key, out);
+                        out.push(':');
+                        value.write_json(out);
+                    }
+                    out.push('}');
+                }
+            }
+        }
+
+        /// Parse JSON. Integers become `U64` (or `I64` when negative), other
+        /// numbers `F64` and `null` `Unit`.
+        pub fn from_json(text: &str) -> Option<Value> {
+            let mut parser = Parser { bytes: text.as_bytes(), pos: 0 };
+            let value = parser.value(0)?;
+            parser.skip_whitespace();
+            (parser.pos == parser.bytes.len()).then_some(value)
+        }
     }
-    
+
+    fn write_string(s: &str, out: &mut String) {
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+
+    struct Parser<'a> {
+        bytes: &'a [u8],
+        pos: usize,
+    }
+
+    impl Parser<'_> {
+        fn skip_whitespace(&mut self) {
+            while matches!(self.bytes.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+                self.pos += 1;
+            }
+        }
+
+        fn eat(&mut self, literal: &str) -> bool {
+            if self.bytes[self.pos..].starts_with(literal.as_bytes()) {
+                self.pos += literal.len();
+                true
+            } else {
+                false
+            }
+        }
+
+        fn value(&mut self, depth: usize) -> Option<Value> {
+            if depth > MAX_DEPTH {
+                return None;
+            }
+            self.skip_whitespace();
+            match *self.bytes.get(self.pos)? {
+                b'{' => {
+                    self.pos += 1;
+                    let mut map = HashMap::new();
+                    self.skip_whitespace();
+                    if self.eat("}") {
+                        return Some(Value::Map(map));
+                    }
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.string()?;
+                        self.skip_whitespace();
+                        if !self.eat(":") { return None; }
+                        map.insert(key, self.value(depth + 1)?);
+                        self.skip_whitespace();
+                        if self.eat("}") { return Some(Value::Map(map)); }
+                        if !self.eat(",") { return None; }
+                    }
+                }
+                b'[' => {
+                    self.pos += 1;
+                    let mut items = Vec::new();
+                    self.skip_whitespace();
+                    if self.eat("]") {
+                        return Some(Value::Seq(items));
+                    }
+                    loop {
+                        items.push(self.value(depth + 1)?);
+                        self.skip_whitespace();
+                        if self.eat("]") { return Some(Value::Seq(items)); }
+                        if !self.eat(",") { return None; }
+                    }
+                }
+                b'"' => self.string().map(Value::String),
+                b't' if self.eat("true") => Some(Value::Bool(true)),
+                b'f' if self.eat("false") => Some(Value::Bool(false)),
+                b'n' if self.eat("null") => Some(Value::Unit),
+                b'-' | b'0'..=b'9' => self.number(),
+                _ => None,
+            }
+        }
+
+        fn number(&mut self) -> Option<Value> {
+            let start = self.pos;
+            while matches!(self.bytes.get(self.pos), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+                self.pos += 1;
+            }
+            let text = std::str::from_utf8(&self.bytes[start..self.pos]).ok()?;
+            if !text.contains(['.', 'e', 'E']) {
+                if let Ok(n) = text.parse::<u64>() {
+                    return Some(Value::U64(n));
+                }
+                if let Ok(n) = text.parse::<i64>() {
+                    return Some(Value::I64(n));
+                }
+            }
+            text.parse::<f64>().ok().filter(|n| n.is_finite()).map(Value::F64)
+        }
+
+        fn string(&mut self) -> Option<String> {
+            if !self.eat("\"") {
+                return None;
+            }
+            let mut out = String::new();
+            loop {
+                let start = self.pos;
+                while !matches!(self.bytes.get(self.pos), Some(b'"' | b'\\') | None) {
+                    self.pos += 1;
+                }
+                out.push_str(std::str::from_utf8(&self.bytes[start..self.pos]).ok()?);
+                match *self.bytes.get(self.pos)? {
+                    b'"' => {
+                        self.pos += 1;
+                        return Some(out);
+                    }
+                    _ => {
+                        let escape = *self.bytes.get(self.pos + 1)?;
+                        self.pos += 2;
+                        match escape {
+                            b'"' => out.push('"'),
+                            b'\\' => out.push('\\'),
+                            b'/' => out.push('/'),
+                            b'b' => out.push('\u{8}'),
+                            b'f' => out.push('\u{C}'),
+                            b'n' => out.push('\n'),
+                            b'r' => out.push('\r'),
+                            b't' => out.push('\t'),
+                            b'u' => {
+                                let mut code = self.hex4()?;
+                                // Surrogate pair
+                                if (0xD800..0xDC00).contains(&code) && self.eat("\\u") {
+                                    let low = self.hex4()?;
+                                    if !(0xDC00..0xE000).contains(&low) { return None; }
+                                    code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                                }
+                                out.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                            }
+                            _ => return None,
+                        }
+                    }
+                }
+            }
+        }
+
+        fn hex4(&mut self) -> Option<u32> {
+            let digits = self.bytes.get(self.pos..self.pos + 4)?;
+            self.pos += 4;
+            u32::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+        }
+    }
+
     impl From<&str> for Value {
         fn from(s: &str) -> Self {
             Value::String(s.to_string())
         }
     }
-    
+
     impl From<String> for Value {
         fn from(s: String) -> Self {
             Value::String(s)
         }
     }
+
+    impl From<bool> for Value {
+        fn from(b: bool) -> Self {
+            Value::Bool(b)
+        }
+    }
+
+    impl From<u64> for Value {
+        fn from(n: u64) -> Self {
+            Value::U64(n)
+        }
+    }
+
+    impl From<f64> for Value {
+        fn from(n: f64) -> Self {
+            Value::F64(n)
+        }
+    }
 }
 
 fn current_time() -> f64 {
@@ -708,37 +2160,230 @@ fn current_time() -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+    use crate::debugger::Scope;
+
     #[test]
     fn test_cdp_server_creation() {
         let server = CdpServer::new();
         assert!(server.sessions.is_empty());
     }
-    
+
     #[test]
     fn test_cdp_session_creation() {
         let mut server = CdpServer::new();
         let session_id = server.create_session();
         assert!(server.sessions.contains_key(&session_id));
     }
-    
+
     #[test]
     fn test_runtime_enable() {
         let mut server = CdpServer::new();
         let cmd = CdpCommand::new(1, "Runtime.enable");
         let response = server.handle_command(cmd);
-        
+
         assert!(response.error.is_none());
         assert!(server.is_domain_enabled("Runtime"));
     }
-    
+
     #[test]
     fn test_unknown_method() {
         let mut server = CdpServer::new();
         let cmd = CdpCommand::new(1, "Unknown.method");
         let response = server.handle_command(cmd);
-        
+
         assert!(response.error.is_some());
         assert_eq!(response.error.unwrap().code, -32601);
     }
+
+    #[test]
+    fn test_json_round_trip() {
+        let cmd = CdpCommand::from_json(r#"{"id": 7, "method": "DOM.querySelector", "params": {"nodeId": 1, "selector": "a\"é😀", "depth": -1}, "sessionId": "s"}"#).unwrap();
+        assert_eq!((cmd.id, cmd.method.as_str(), cmd.session_id.as_deref()), (7, "DOM.querySelector", Some("s")));
+        assert_eq!(cmd.param("selector").and_then(Value::as_str), Some("a\"é😀"));
+        assert_eq!(cmd.param("depth").and_then(Value::as_i64), Some(-1));
+        assert_eq!(CdpCommand::from_json("{\"id\": 1}").unwrap_err().code, -32600);
+        assert_eq!(CdpCommand::from_json("{\"id\": 1,").unwrap_err().code, -32700);
+
+        let mut response = CdpResponse::success(3, Value::Unit);
+        response.session_id = Some("s".into());
+        let json = Value::from_json(&response.to_json()).unwrap();
+        assert_eq!(json.get("id").and_then(Value::as_u64), Some(3));
+        assert!(matches!(json.get("result"), Some(Value::Map(m)) if m.is_empty()));
+        assert_eq!(object([("text", "line\n\u{1}".into())]).to_json(), r#"{"text":"line\n\u0001"}"#);
+    }
+
+    #[test]
+    fn test_dom_and_css_domains() {
+        let mut server = CdpServer::new();
+        let mut doc = InspectedNode::element(1, "#document");
+        doc.node_type = NodeType::Document;
+        doc.children = vec![2];
+        let mut body = InspectedNode::element(2, "body");
+        body.parent = Some(1);
+        body.attributes.insert("style".into(), "color: red !important; margin: 0".into());
+        body.computed_styles.insert("display".into(), "block".into());
+        server.inspector.add_node(doc);
+        server.inspector.add_node(body);
+        server.handle_command(CdpCommand::new(1, "DOM.enable"));
+
+        let document = server.handle_command(CdpCommand::new(2, "DOM.getDocument")).result.unwrap();
+        let root = document.get("root").unwrap();
+        assert_eq!(root.get("nodeName").and_then(Value::as_str), Some("#document"));
+        let Some(Value::Seq(children)) = root.get("children") else { panic!("children missing") };
+        assert_eq!(children[0].get("nodeName").and_then(Value::as_str), Some("BODY"));
+
+        let found = server.handle_command(CdpCommand::new(3, "DOM.querySelector")
+            .with_param("nodeId", Value::U64(1)).with_param("selector", "body".into())).result.unwrap();
+        assert_eq!(found.get("nodeId").and_then(Value::as_u64), Some(2));
+
+        server.handle_command(CdpCommand::new(4, "DOM.setAttributeValue")
+            .with_param("nodeId", Value::U64(2)).with_param("name", "id".into()).with_param("value", "b".into()));
+        assert_eq!(server.take_events()[0].method, "DOM.attributeModified");
+
+        let styles = server.handle_command(CdpCommand::new(5, "CSS.getMatchedStylesForNode")
+            .with_param("nodeId", Value::U64(2))).result.unwrap();
+        let Some(Value::Seq(inline)) = styles.get("inlineStyle").and_then(|s| s.get("cssProperties")) else { panic!("inline style missing") };
+        assert_eq!(inline[0].get("important").and_then(Value::as_bool), Some(true));
+        let missing = server.handle_command(CdpCommand::new(6, "CSS.getComputedStyleForNode").with_param("nodeId", Value::U64(9)));
+        assert_eq!(missing.error.unwrap().code, -32000);
+
+        let mut text = InspectedNode::text(3, "hi");
+        text.parent = Some(2);
+        server.inspector.add_node(text);
+        server.handle_command(CdpCommand::new(7, "DOM.setNodeValue")
+            .with_param("nodeId", Value::U64(3)).with_param("value", "bye".into()));
+        let forced = server.handle_command(CdpCommand::new(8, "CSS.forcePseudoState")
+            .with_param("nodeId", Value::U64(2)).with_param("forcedPseudoClasses", Value::Seq(vec!["hover".into()])));
+        assert!(forced.error.is_none());
+        assert_eq!(server.inspector.forced_pseudo_state(2), ["hover".to_string()]);
+        server.handle_command(CdpCommand::new(9, "Overlay.highlightNode").with_param("nodeId", Value::U64(2)));
+        let no_box = server.handle_command(CdpCommand::new(10, "DOM.getBoxModel").with_param("nodeId", Value::U64(2)));
+        assert!(no_box.error.is_some());
+        server.handle_command(CdpCommand::new(11, "DOM.removeNode").with_param("nodeId", Value::U64(3)));
+        let events: Vec<_> = server.take_events().into_iter().map(|e| e.method).collect();
+        assert_eq!(events, ["DOM.characterDataModified", "DOM.childNodeRemoved"]);
+        assert!(server.inspector.get_node(3).is_none());
+        assert_eq!(server.inspector.take_edits().len(), 4);
+    }
+
+    #[test]
+    fn test_panel_events() {
+        let mut server = CdpServer::new();
+        server.console.log("before", vec![]);
+        server.handle_command(CdpCommand::new(1, "Runtime.enable"));
+        server.handle_command(CdpCommand::new(2, "Network.enable"));
+        server.handle_command(CdpCommand::new(3, "Debugger.enable"));
+        let methods: Vec<String> = server.take_events().into_iter().map(|e| e.method).collect();
+        assert_eq!(methods, ["Runtime.executionContextCreated", "Runtime.consoleAPICalled"]);
+
+        server.console.warn("careful", vec![ConsoleValue::Array(vec![ConsoleValue::Number(1.0)])]);
+        let request = server.network.log_request("https://example.com/app.js", "GET", HashMap::new());
+        server.network.log_response(request, 200, "OK", HashMap::from([("content-type".to_string(), "text/javascript".to_string())]));
+        server.debugger.set_call_stack(vec![CallFrame {
+            id: 4, function_name: "main".into(), url: "app.js".into(), line: 2, column: 0,
+            scope_chain: vec![Scope { scope_type: ScopeType::Local, name: None, variables: HashMap::from([
+                ("x".to_string(), VariableValue { name: "x".into(), value_type: "number".into(), value: "5".into(), expandable: false }),
+            ]) }],
+            this_value: None,
+        }]);
+        server.debugger.add_breakpoint("app.js", 2);
+        server.debugger.pause();
+        server.collect_events();
+        let events = server.take_events();
+        let methods: Vec<&str> = events.iter().map(|e| e.method.as_str()).collect();
+        assert_eq!(methods, ["Runtime.consoleAPICalled", "Network.requestWillBeSent", "Network.responseReceived", "Network.loadingFinished", "Debugger.paused"]);
+        assert_eq!(events[4].params.get("reason").and_then(Value::as_str), Some("breakpoint"));
+
+        // The array argument and the paused frame's scope can be expanded
+        let Some(Value::Seq(args)) = events[0].params.get("args") else { panic!("args missing") };
+        let array_id = args[1].get("objectId").and_then(Value::as_str).unwrap().to_string();
+        let items = server.handle_command(CdpCommand::new(4, "Runtime.getProperties").with_param("objectId", array_id.into())).result.unwrap();
+        let Some(Value::Seq(items)) = items.get("result") else { panic!("properties missing") };
+        assert_eq!(items[0].get("value").and_then(|v| v.get("value")).and_then(Value::as_f64), Some(1.0));
+        let scope = server.handle_command(CdpCommand::new(5, "Runtime.getProperties").with_param("objectId", "scope-4-0".into())).result.unwrap();
+        assert!(scope.to_json().contains("\"name\":\"x\""));
+
+        server.handle_command(CdpCommand::new(6, "Debugger.resume"));
+        server.collect_events();
+        assert_eq!(server.take_events()[0].method, "Debugger.resumed");
+    }
+
+    #[test]
+    fn test_tracing_domain() {
+        use crate::trace::TraceKind;
+
+        let mut server = CdpServer::new();
+        assert!(server.handle_command(CdpCommand::new(1, "Tracing.end")).error.is_some());
+        server.handle_command(CdpCommand::new(2, "Tracing.start"));
+        server.performance.begin_frame(1);
+        let span = server.performance.tracer_mut().begin(TraceKind::Layout);
+        server.performance.tracer_mut().end(span);
+        server.performance.end_frame();
+        server.handle_command(CdpCommand::new(3, "Tracing.end"));
+
+        let events = server.take_events();
+        assert_eq!(events[1].method, "Tracing.tracingComplete");
+        let Some(Value::Seq(trace)) = events[0].params.get("value") else { panic!("trace missing") };
+        assert!(trace.iter().any(|e| e.get("name").and_then(Value::as_str) == Some("Layout")));
+        assert_eq!(server.performance.frame_timeline().frames.len(), 1);
+    }
+
+    #[test]
+    fn test_coverage_domains() {
+        use crate::{CoverageEntry, CoverageType};
+
+        let mut server = CdpServer::new();
+        server.handle_command(CdpCommand::new(1, "Profiler.startPreciseCoverage"));
+        server.handle_command(CdpCommand::new(2, "CSS.startRuleUsageTracking"));
+        server.coverage.record(CoverageEntry::new("app.js", CoverageType::Js, 40)
+            .with_range("", 0..40, 1)
+            .with_range("unused", 5..20, 0));
+        server.coverage.record(CoverageEntry::new("app.css", CoverageType::Css, 30)
+            .with_range("p", 0..10, 1));
+
+        let taken = server.handle_command(CdpCommand::new(3, "Profiler.takePreciseCoverage")).result.unwrap();
+        let Some(Value::Seq(scripts)) = taken.get("result") else { panic!("coverage missing") };
+        let Some(Value::Seq(functions)) = scripts[0].get("functions") else { panic!("functions missing") };
+        assert_eq!(functions[1].get("functionName").and_then(Value::as_str), Some("unused"));
+        let Some(Value::Seq(ranges)) = functions[1].get("ranges") else { panic!("ranges missing") };
+        assert_eq!(ranges[0].get("count").and_then(Value::as_u64), Some(0));
+
+        let usage = server.handle_command(CdpCommand::new(4, "CSS.stopRuleUsageTracking")).result.unwrap();
+        let Some(Value::Seq(rules)) = usage.get("ruleUsage") else { panic!("rule usage missing") };
+        assert_eq!(rules[0].get("used").and_then(Value::as_bool), Some(true));
+        assert!(!server.coverage.is_recording(CoverageType::Css));
+    }
+
+    #[test]
+    fn test_accessibility_domain() {
+        let mut server = CdpServer::new();
+        let mut document = AxNode::new(1, "document");
+        document.children = vec![2];
+        let mut button = AxNode::new(2, "button");
+        button.parent = Some(1);
+        button.dom_node = Some(7);
+        button.name = "Menu".to_string();
+        button.states = vec!["focusable".to_string(), "collapsed".to_string()];
+        server.accessibility.set_tree(1, vec![document, button]);
+
+        server.handle_command(CdpCommand::new(1, "Accessibility.enable"));
+        let full = server.handle_command(CdpCommand::new(2, "Accessibility.getFullAXTree")).result.unwrap();
+        let Some(Value::Seq(nodes)) = full.get("nodes") else { panic!("nodes missing") };
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[1].get("role").and_then(|r| r.get("value")).and_then(Value::as_str), Some("button"));
+        assert_eq!(nodes[1].get("name").and_then(|n| n.get("value")).and_then(Value::as_str), Some("Menu"));
+        assert_eq!(nodes[1].get("backendDOMNodeId").and_then(Value::as_u64), Some(7));
+        let Some(Value::Seq(properties)) = nodes[1].get("properties") else { panic!("properties missing") };
+        assert_eq!(properties[1].get("name").and_then(Value::as_str), Some("expanded"));
+        assert_eq!(properties[1].get("value").and_then(|v| v.get("value")).and_then(Value::as_bool), Some(false));
+
+        let partial = server.handle_command(CdpCommand::new(3, "Accessibility.getPartialAXTree")
+            .with_param("backendNodeId", 7u64.into())).result.unwrap();
+        let Some(Value::Seq(nodes)) = partial.get("nodes") else { panic!("nodes missing") };
+        let ids: Vec<&str> = nodes.iter().filter_map(|n| n.get("nodeId").and_then(Value::as_str)).collect();
+        assert_eq!(ids, vec!["2", "1"]);
+        let missing = server.handle_command(CdpCommand::new(4, "Accessibility.getPartialAXTree")
+            .with_param("nodeId", 99u64.into()));
+        assert!(missing.error.is_some());
+    }
 }
@@ -107,6 +107,8 @@ pub struct Console {
     element_history: [Option<u64>; 5],
     /// Current group depth for indentation
     group_depth: usize,
+    /// Messages logged so far, including ones dropped or cleared since
+    logged: u64,
 }
 
 impl Console {
@@ -119,6 +121,7 @@ impl Console {
             last_result: None,
             element_history: [None; 5],
             group_depth: 0,
+            logged: 0,
         }
     }
     
@@ -133,6 +136,7 @@ impl Console {
         };
         
         self.messages.push_back(msg);
+        self.logged += 1;
         while self.messages.len() > self.max_messages {
             self.messages.pop_front();
         }
@@ -174,6 +178,7 @@ impl Console {
             stack_trace: Some(Vec::new()), // Would capture actual stack
         };
         self.messages.push_back(msg);
+        self.logged += 1;
     }
     
     /// console.assert
@@ -253,6 +258,12 @@ impl Console {
         &self.messages
     }
     
+    /// Number of messages logged since creation; messages past
+    /// `logged_count() - get_messages().len()` are still held
+    pub fn logged_count(&self) -> u64 {
+        self.logged
+    }
+    
     /// Get messages by level
     pub fn get_by_level(&self, level: LogLevel) -> Vec<&ConsoleMessage> {
         self.messages.iter().filter(|m| m.level == level).collect()
@@ -0,0 +1,250 @@
+//! Coverage Panel
+//!
+//! Used and unused bytes of each stylesheet and script, for finding dead CSS
+//! and JavaScript. The embedder records the ranges of the rules that matched
+//! and the functions that ran; a byte is used when the innermost range
+//! holding it has a non-zero count.
+
+use std::cmp::Reverse;
+use std::ops::Range;
+use crate::cdp::serde_value::{object, Value};
+
+/// Kind of resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageType {
+    Css,
+    Js,
+}
+
+/// A rule or function in a resource
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageRange {
+    /// Selector or function name
+    pub name: String,
+    /// Start, in bytes into the resource text
+    pub start: usize,
+    /// End, in bytes into the resource text
+    pub end: usize,
+    /// Times the rule matched or the function ran
+    pub count: u64,
+}
+
+/// Coverage of one stylesheet or script
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageEntry {
+    pub url: String,
+    pub kind: CoverageType,
+    /// Size of the resource text
+    pub total_bytes: usize,
+    /// Ranges of rules or functions; ranges nest but do not overlap
+    pub ranges: Vec<CoverageRange>,
+}
+
+impl CoverageEntry {
+    pub fn new(url: &str, kind: CoverageType, total_bytes: usize) -> Self {
+        Self { url: url.to_string(), kind, total_bytes, ranges: Vec::new() }
+    }
+    
+    /// Add a rule or function range
+    pub fn with_range(mut self, name: &str, range: Range<usize>, count: u64) -> Self {
+        self.ranges.push(CoverageRange { name: name.to_string(), start: range.start, end: range.end, count });
+        self
+    }
+    
+    /// Byte ranges whose innermost rule or function was used, merged
+    pub fn used_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<&CoverageRange> = self.ranges.iter().collect();
+        ranges.sort_by_key(|r| (r.start, Reverse(r.end)));
+        
+        let mut used: Vec<Range<usize>> = Vec::new();
+        let mut emit = |from: usize, to: usize, innermost: Option<&&CoverageRange>| {
+            let to = to.min(self.total_bytes);
+            if from >= to || innermost.is_none_or(|r| r.count == 0) {
+                return;
+            }
+            match used.last_mut() {
+                Some(last) if last.end == from => last.end = to,
+                _ => used.push(from..to),
+            }
+        };
+        
+        let mut stack: Vec<&CoverageRange> = Vec::new();
+        let mut pos = 0;
+        for range in ranges {
+            while let Some(top) = stack.last() {
+                if top.end > range.start {
+                    break;
+                }
+                emit(pos, top.end, Some(top));
+                pos = pos.max(top.end);
+                stack.pop();
+            }
+            emit(pos, range.start, stack.last());
+            pos = pos.max(range.start);
+            stack.push(range);
+        }
+        while let Some(top) = stack.pop() {
+            emit(pos, top.end, Some(&top));
+            pos = pos.max(top.end);
+        }
+        used
+    }
+    
+    /// Bytes in used ranges
+    pub fn used_bytes(&self) -> usize {
+        self.used_ranges().iter().map(|r| r.len()).sum()
+    }
+    
+    /// Bytes outside used ranges
+    pub fn unused_bytes(&self) -> usize {
+        self.total_bytes - self.used_bytes()
+    }
+    
+    /// Share of the resource that is unused, 0 to 1
+    pub fn unused_ratio(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
+        self.unused_bytes() as f64 / self.total_bytes as f64
+    }
+}
+
+/// Used and unused bytes of one URL, over all its stylesheets and scripts
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrlCoverage {
+    pub url: String,
+    pub kind: CoverageType,
+    pub total_bytes: usize,
+    pub unused_bytes: usize,
+}
+
+/// Coverage panel
+#[derive(Debug, Default)]
+pub struct CoveragePanel {
+    /// Kinds of resources being recorded
+    recording: Vec<CoverageType>,
+    entries: Vec<CoverageEntry>,
+}
+
+impl CoveragePanel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Start recording one kind of resource, dropping its earlier coverage
+    pub fn start(&mut self, kind: CoverageType) {
+        if !self.recording.contains(&kind) {
+            self.recording.push(kind);
+        }
+        self.entries.retain(|e| e.kind != kind);
+    }
+    
+    /// Stop recording one kind, keeping its coverage for inspection
+    pub fn stop(&mut self, kind: CoverageType) {
+        self.recording.retain(|k| *k != kind);
+    }
+    
+    pub fn is_recording(&self, kind: CoverageType) -> bool {
+        self.recording.contains(&kind)
+    }
+    
+    /// Record the coverage of a resource, replacing earlier coverage of the
+    /// same URL, kind and size; ignored when its kind is not recorded
+    pub fn record(&mut self, entry: CoverageEntry) {
+        if !self.is_recording(entry.kind) {
+            return;
+        }
+        let same = |e: &CoverageEntry| e.url == entry.url && e.kind == entry.kind && e.total_bytes == entry.total_bytes;
+        match self.entries.iter_mut().find(|e| same(e)) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+    
+    /// Recorded stylesheets and scripts
+    pub fn entries(&self) -> &[CoverageEntry] {
+        &self.entries
+    }
+    
+    /// Coverage per URL and kind, most unused bytes first
+    pub fn report(&self) -> Vec<UrlCoverage> {
+        let mut report: Vec<UrlCoverage> = Vec::new();
+        for entry in &self.entries {
+            let unused = entry.unused_bytes();
+            match report.iter_mut().find(|r| r.url == entry.url && r.kind == entry.kind) {
+                Some(url) => {
+                    url.total_bytes += entry.total_bytes;
+                    url.unused_bytes += unused;
+                }
+                None => report.push(UrlCoverage {
+                    url: entry.url.clone(),
+                    kind: entry.kind,
+                    total_bytes: entry.total_bytes,
+                    unused_bytes: unused,
+                }),
+            }
+        }
+        report.sort_by_key(|r| Reverse(r.unused_bytes));
+        report
+    }
+    
+    /// Total and unused bytes over everything recorded
+    pub fn totals(&self) -> (usize, usize) {
+        self.entries.iter().fold((0, 0), |(total, unused), e| (total + e.total_bytes, unused + e.unused_bytes()))
+    }
+    
+    /// Export as JSON in the layout of Chrome's coverage export: one object
+    /// per resource with its used ranges
+    pub fn export_json(&self) -> String {
+        let entries = self.entries.iter().map(|entry| {
+            let ranges = entry.used_ranges().into_iter()
+                .map(|r| object([("start", (r.start as u64).into()), ("end", (r.end as u64).into())]))
+                .collect();
+            object([
+                ("url", entry.url.as_str().into()),
+                ("type", match entry.kind { CoverageType::Css => "css", CoverageType::Js => "js" }.into()),
+                ("totalBytes", (entry.total_bytes as u64).into()),
+                ("ranges", Value::Seq(ranges)),
+            ])
+        }).collect();
+        Value::Seq(entries).to_json()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_nested_function_coverage() {
+        // Top-level code ran, one of its two functions never did
+        let script = CoverageEntry::new("app.js", CoverageType::Js, 100)
+            .with_range("", 0..100, 1)
+            .with_range("used", 10..30, 2)
+            .with_range("unused", 40..70, 0)
+            .with_range("inner", 50..60, 3);
+        assert_eq!(script.used_ranges(), vec![0..40, 50..60, 70..100]);
+        assert_eq!(script.unused_bytes(), 20);
+        
+        let sheet = CoverageEntry::new("app.css", CoverageType::Css, 50)
+            .with_range("a", 0..20, 1)
+            .with_range("b", 20..45, 0);
+        assert_eq!(sheet.used_ranges(), vec![0..20]);
+        assert_eq!(sheet.unused_bytes(), 30);
+        
+        let mut panel = CoveragePanel::new();
+        panel.start(CoverageType::Js);
+        panel.record(sheet.clone());
+        assert!(panel.entries().is_empty());
+        panel.start(CoverageType::Css);
+        panel.record(script);
+        panel.record(sheet);
+        assert_eq!(panel.totals(), (150, 50));
+        let report = panel.report();
+        assert_eq!((report[0].url.as_str(), report[0].unused_bytes), ("app.css", 30));
+        
+        let json = Value::from_json(&panel.export_json()).unwrap();
+        let Value::Seq(entries) = json else { panic!("expected an array") };
+        assert_eq!(entries[0].get("type").and_then(Value::as_str), Some("js"));
+    }
+}
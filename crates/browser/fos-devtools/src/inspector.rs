@@ -3,6 +3,7 @@
 //! DOM tree and style inspection.
 
 use std::collections::HashMap;
+use crate::elements::BoxModel;
 
 /// Inspected node
 #[derive(Debug, Clone)]
@@ -76,6 +77,31 @@ impl InspectedNode {
         
         selector
     }
+    
+    /// Attributes as written in markup: `id` and `class` first, then the
+    /// rest by name
+    pub fn attribute_list(&self) -> Vec<(String, String)> {
+        let mut list = Vec::new();
+        if let Some(id) = self.id_attr.as_ref().or_else(|| self.attributes.get("id")) {
+            list.push(("id".to_string(), id.clone()));
+        }
+        if !self.class_list.is_empty() {
+            list.push(("class".to_string(), self.class_list.join(" ")));
+        } else if let Some(class) = self.attributes.get("class") {
+            list.push(("class".to_string(), class.clone()));
+        }
+        let mut rest: Vec<_> = self.attributes.iter()
+            .filter(|(name, _)| *name != "id" && *name != "class")
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        rest.sort();
+        list.extend(rest);
+        list
+    }
+    
+    fn attribute(&self, name: &str) -> Option<String> {
+        self.attribute_list().into_iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
 }
 
 /// Style rule
@@ -90,6 +116,8 @@ pub struct InspectedStyleRule {
 #[derive(Debug, Clone)]
 pub struct StyleSource {
     pub url: Option<String>,
+    /// Browser default stylesheet rather than page CSS
+    pub user_agent: bool,
     pub line: u32,
     pub column: u32,
     pub is_inline: bool,
@@ -104,12 +132,27 @@ pub struct StyleProperty {
     pub overridden: bool,
 }
 
+/// Edit made from DevTools that the embedder applies to the live DOM
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomEdit {
+    SetAttribute { node: u64, name: String, value: String },
+    RemoveAttribute { node: u64, name: String },
+    SetText { node: u64, text: String },
+    RemoveNode { node: u64 },
+    /// Interactive pseudo-classes to match as if the user caused them
+    ForcePseudoState { node: u64, states: Vec<String> },
+}
+
 /// Element inspector
 #[derive(Debug, Default)]
 pub struct Inspector {
     nodes: HashMap<u64, InspectedNode>,
     selected: Option<u64>,
     styles_cache: HashMap<u64, Vec<InspectedStyleRule>>,
+    pseudo_states: HashMap<u64, Vec<String>>,
+    box_models: HashMap<u64, BoxModel>,
+    highlighted: Option<u64>,
+    edits: Vec<DomEdit>,
 }
 
 impl Inspector {
@@ -154,6 +197,205 @@ impl Inspector {
         self.styles_cache.insert(id, rules);
     }
     
+    /// Replace the computed styles of a node
+    pub fn set_computed_styles(&mut self, id: u64, styles: HashMap<String, String>) {
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.computed_styles = styles;
+        }
+    }
+    
+    /// Set the layout box of a node
+    pub fn set_box_model(&mut self, id: u64, model: BoxModel) {
+        self.box_models.insert(id, model);
+    }
+    
+    /// Layout box of a node
+    pub fn get_box_model(&self, id: u64) -> Option<&BoxModel> {
+        self.box_models.get(&id)
+    }
+    
+    /// Highlight a node in the page, or clear the highlight
+    pub fn highlight(&mut self, id: Option<u64>) {
+        self.highlighted = id.filter(|id| self.nodes.contains_key(id));
+    }
+    
+    /// Highlighted node and its layout box, if it has one
+    pub fn highlighted(&self) -> Option<(u64, &BoxModel)> {
+        let id = self.highlighted?;
+        self.box_models.get(&id).map(|model| (id, model))
+    }
+    
+    /// Document node: the Document, or else the parentless node with the
+    /// lowest id
+    pub fn root(&self) -> Option<&InspectedNode> {
+        self.nodes.values().find(|n| n.node_type == NodeType::Document)
+            .or_else(|| self.nodes.values().filter(|n| n.parent.is_none()).min_by_key(|n| n.id))
+    }
+    
+    /// Set an attribute, keeping `id_attr` and `class_list` in step
+    pub fn set_attribute(&mut self, id: u64, name: &str, value: &str) -> bool {
+        let Some(node) = self.nodes.get_mut(&id) else { return false };
+        match name {
+            "id" => node.id_attr = Some(value.to_string()),
+            "class" => node.class_list = value.split_whitespace().map(String::from).collect(),
+            _ => {}
+        }
+        node.attributes.insert(name.to_string(), value.to_string());
+        self.edits.push(DomEdit::SetAttribute { node: id, name: name.to_string(), value: value.to_string() });
+        true
+    }
+    
+    /// Remove an attribute
+    pub fn remove_attribute(&mut self, id: u64, name: &str) -> bool {
+        let Some(node) = self.nodes.get_mut(&id) else { return false };
+        match name {
+            "id" => node.id_attr = None,
+            "class" => node.class_list.clear(),
+            _ => {}
+        }
+        node.attributes.remove(name);
+        self.edits.push(DomEdit::RemoveAttribute { node: id, name: name.to_string() });
+        true
+    }
+    
+    /// Replace the content of a text or comment node
+    pub fn set_text(&mut self, id: u64, text: &str) -> bool {
+        let Some(node) = self.nodes.get_mut(&id) else { return false };
+        if !matches!(node.node_type, NodeType::Text | NodeType::Comment) {
+            return false;
+        }
+        node.text_content = Some(text.to_string());
+        self.edits.push(DomEdit::SetText { node: id, text: text.to_string() });
+        true
+    }
+    
+    /// Detach a node from its parent and forget its subtree
+    pub fn remove_node(&mut self, id: u64) -> bool {
+        let Some(node) = self.nodes.get(&id) else { return false };
+        if node.node_type == NodeType::Document {
+            return false;
+        }
+        if let Some(parent) = node.parent.and_then(|p| self.nodes.get_mut(&p)) {
+            parent.children.retain(|&c| c != id);
+        }
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.nodes.remove(&id) {
+                stack.extend(node.children);
+            }
+            self.styles_cache.remove(&id);
+            self.pseudo_states.remove(&id);
+            self.box_models.remove(&id);
+            if self.selected == Some(id) {
+                self.selected = None;
+            }
+            if self.highlighted == Some(id) {
+                self.highlighted = None;
+            }
+        }
+        self.edits.push(DomEdit::RemoveNode { node: id });
+        true
+    }
+    
+    /// Force `:hover`, `:focus` and similar states on an element; an empty
+    /// list clears them
+    pub fn force_pseudo_state(&mut self, id: u64, states: &[&str]) -> bool {
+        if self.nodes.get(&id).map(|n| n.node_type) != Some(NodeType::Element) {
+            return false;
+        }
+        let states: Vec<String> = states.iter().map(|s| s.trim_start_matches(':').to_string()).collect();
+        if states.is_empty() {
+            self.pseudo_states.remove(&id);
+        } else {
+            self.pseudo_states.insert(id, states.clone());
+        }
+        self.edits.push(DomEdit::ForcePseudoState { node: id, states });
+        true
+    }
+    
+    /// Pseudo-classes forced on an element
+    pub fn forced_pseudo_state(&self, id: u64) -> &[String] {
+        self.pseudo_states.get(&id).map(Vec::as_slice).unwrap_or(&[])
+    }
+    
+    /// Take the edits made since the last call, oldest first
+    pub fn take_edits(&mut self) -> Vec<DomEdit> {
+        std::mem::take(&mut self.edits)
+    }
+    
+    /// Elements below `root` matching a selector list, in document order.
+    /// Compound selectors of type, `#id`, `.class` and `[attr]`/`[attr=value]`
+    /// may be joined by descendant and child combinators.
+    pub fn query_selector_all(&self, root: u64, selector: &str) -> Vec<u64> {
+        let selectors: Vec<Vec<(Combinator, Compound)>> = selector.split(',').filter_map(parse_complex).collect();
+        let mut matches = Vec::new();
+        let mut stack: Vec<u64> = self.nodes.get(&root).map(|n| n.children.iter().rev().copied().collect()).unwrap_or_default();
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.nodes.get(&id) else { continue };
+            if node.node_type == NodeType::Element && selectors.iter().any(|s| self.matches(node, s)) {
+                matches.push(id);
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        matches
+    }
+    
+    /// Whether `node` matches a complex selector, tested right to left
+    fn matches(&self, node: &InspectedNode, selector: &[(Combinator, Compound)]) -> bool {
+        let Some(((combinator, compound), rest)) = selector.split_last() else { return true };
+        if !compound.matches(node) {
+            return false;
+        }
+        if rest.is_empty() {
+            return true;
+        }
+        let mut parent = node.parent.and_then(|p| self.nodes.get(&p));
+        while let Some(ancestor) = parent {
+            if self.matches(ancestor, rest) {
+                return true;
+            }
+            if *combinator == Combinator::Child {
+                return false;
+            }
+            parent = ancestor.parent.and_then(|p| self.nodes.get(&p));
+        }
+        false
+    }
+    
+    /// Markup for a node and its subtree
+    pub fn outer_html(&self, id: u64) -> Option<String> {
+        let node = self.nodes.get(&id)?;
+        let mut html = String::new();
+        match node.node_type {
+            NodeType::Element => {
+                html.push('<');
+                html.push_str(&node.tag_name);
+                for (name, value) in node.attribute_list() {
+                    html.push_str(&format!(" {}=\"{}\"", name, value.replace('&', "&amp;").replace('"', "&quot;")));
+                }
+                html.push('>');
+                for child in &node.children {
+                    html.push_str(&self.outer_html(*child).unwrap_or_default());
+                }
+                if !is_void_element(&node.tag_name) {
+                    html.push_str(&format!("</{}>", node.tag_name));
+                }
+            }
+            NodeType::Text => {
+                let text = node.text_content.as_deref().unwrap_or("");
+                html.push_str(&text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;"));
+            }
+            NodeType::Comment => html.push_str(&format!("<!--{}-->", node.text_content.as_deref().unwrap_or(""))),
+            NodeType::DocumentType => html.push_str("<!DOCTYPE html>"),
+            NodeType::Document => {
+                for child in &node.children {
+                    html.push_str(&self.outer_html(*child).unwrap_or_default());
+                }
+            }
+        }
+        Some(html)
+    }
+    
     /// Get DOM tree as string
     pub fn get_dom_tree(&self, root_id: u64, depth: usize) -> String {
         let mut result = String::new();
@@ -189,6 +431,93 @@ impl Inspector {
     }
 }
 
+/// Relation of a compound selector to the one before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+/// Compound selector: type, ids, classes and attribute tests
+#[derive(Debug, Default)]
+struct Compound {
+    tag: Option<String>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+    attributes: Vec<(String, Option<String>)>,
+}
+
+impl Compound {
+    fn parse(text: &str) -> Option<Self> {
+        let mut compound = Self::default();
+        let mut rest = text;
+        let name_len = |s: &str| s.find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_')).unwrap_or(s.len());
+        let tag_len = name_len(rest);
+        if tag_len > 0 {
+            compound.tag = Some(rest[..tag_len].to_ascii_lowercase());
+            rest = &rest[tag_len..];
+        } else if let Some(r) = rest.strip_prefix('*') {
+            rest = r;
+        }
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            match c {
+                '#' | '.' => {
+                    let len = name_len(rest);
+                    if len == 0 { return None; }
+                    let list = if c == '#' { &mut compound.ids } else { &mut compound.classes };
+                    list.push(rest[..len].to_string());
+                    rest = &rest[len..];
+                }
+                '[' => {
+                    let end = rest.find(']')?;
+                    let (name, value) = match rest[..end].split_once('=') {
+                        Some((name, value)) => (name, Some(value.trim().trim_matches(['"', '\'']).to_string())),
+                        None => (&rest[..end], None),
+                    };
+                    compound.attributes.push((name.trim().to_ascii_lowercase(), value));
+                    rest = &rest[end + 1..];
+                }
+                _ => return None,
+            }
+        }
+        Some(compound)
+    }
+    
+    fn matches(&self, node: &InspectedNode) -> bool {
+        self.tag.as_ref().is_none_or(|t| node.tag_name.eq_ignore_ascii_case(t))
+            && self.ids.iter().all(|id| node.attribute("id").as_ref() == Some(id))
+            && self.classes.iter().all(|c| node.attribute("class").is_some_and(|list| list.split_whitespace().any(|x| x == c)))
+            && self.attributes.iter().all(|(name, value)| match (node.attribute(name), value) {
+                (Some(actual), Some(expected)) => actual == *expected,
+                (found, None) => found.is_some(),
+                (None, Some(_)) => false,
+            })
+    }
+}
+
+/// Compound selectors of one complex selector, each with the combinator
+/// linking it to the previous one
+fn parse_complex(text: &str) -> Option<Vec<(Combinator, Compound)>> {
+    let spaced = text.replace('>', " > ");
+    let mut parts = Vec::new();
+    let mut combinator = Combinator::Descendant;
+    for token in spaced.split_whitespace() {
+        if token == ">" {
+            combinator = Combinator::Child;
+            continue;
+        }
+        parts.push((combinator, Compound::parse(token)?));
+        combinator = Combinator::Descendant;
+    }
+    (!parts.is_empty()).then_some(parts)
+}
+
+fn is_void_element(tag: &str) -> bool {
+    matches!(tag.to_ascii_lowercase().as_str(),
+        "area" | "base" | "br" | "col" | "embed" | "hr" | "img" | "input" | "link" | "meta" | "source" | "track" | "wbr")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -207,4 +536,75 @@ mod tests {
         let selected = inspector.get_selected().unwrap();
         assert_eq!(selected.get_selector(), "div#main.container");
     }
+    
+    #[test]
+    fn test_query_selector_and_markup() {
+        let mut inspector = Inspector::new();
+        let mut doc = InspectedNode::element(1, "#document");
+        doc.node_type = NodeType::Document;
+        doc.children = vec![2];
+        let mut body = InspectedNode::element(2, "body");
+        body.parent = Some(1);
+        body.children = vec![3, 5];
+        let mut list = InspectedNode::element(3, "ul");
+        list.class_list = vec!["menu".into()];
+        list.parent = Some(2);
+        list.children = vec![4];
+        let mut item = InspectedNode::element(4, "li");
+        item.attributes.insert("data-x".into(), "1".into());
+        item.parent = Some(3);
+        let mut text = InspectedNode::text(5, "a < b");
+        text.parent = Some(2);
+        for node in [doc, body, list, item, text] {
+            inspector.add_node(node);
+        }
+        
+        assert_eq!(inspector.root().unwrap().id, 1);
+        assert_eq!(inspector.query_selector_all(1, "ul.menu > li[data-x='1']"), vec![4]);
+        assert_eq!(inspector.query_selector_all(1, "body li, ul"), vec![3, 4]);
+        assert!(inspector.query_selector_all(1, "body > li").is_empty());
+        
+        inspector.set_attribute(3, "id", "nav");
+        assert_eq!(
+            inspector.outer_html(2).unwrap(),
+            "<body><ul id=\"nav\" class=\"menu\"><li data-x=\"1\"></li></ul>a &lt; b</body>"
+        );
+    }
+    
+    #[test]
+    fn test_live_edits() {
+        let mut inspector = Inspector::new();
+        let mut div = InspectedNode::element(1, "div");
+        div.children = vec![2, 3];
+        let mut text = InspectedNode::text(2, "old");
+        text.parent = Some(1);
+        let mut span = InspectedNode::element(3, "span");
+        span.parent = Some(1);
+        span.children = vec![4];
+        let mut inner = InspectedNode::text(4, "x");
+        inner.parent = Some(3);
+        for node in [div, text, span, inner] {
+            inspector.add_node(node);
+        }
+        inspector.set_box_model(3, BoxModel::default());
+        inspector.highlight(Some(3));
+        assert_eq!(inspector.highlighted().map(|(id, _)| id), Some(3));
+        
+        assert!(inspector.set_text(2, "new"));
+        assert!(!inspector.set_text(1, "nope"));
+        assert!(inspector.force_pseudo_state(3, &[":hover", "focus"]));
+        assert_eq!(inspector.forced_pseudo_state(3), ["hover".to_string(), "focus".to_string()]);
+        assert!(inspector.remove_node(3));
+        
+        assert_eq!(inspector.outer_html(1).unwrap(), "<div>new</div>");
+        assert!(inspector.get_node(4).is_none());
+        assert!(inspector.highlighted().is_none());
+        assert!(inspector.forced_pseudo_state(3).is_empty());
+        assert_eq!(inspector.take_edits(), vec![
+            DomEdit::SetText { node: 2, text: "new".into() },
+            DomEdit::ForcePseudoState { node: 3, states: vec!["hover".into(), "focus".into()] },
+            DomEdit::RemoveNode { node: 3 },
+        ]);
+        assert!(inspector.take_edits().is_empty());
+    }
 }
@@ -5,41 +5,53 @@
 //! Features:
 //! - Console (log, warn, error)
 //! - Element inspector
-//! - Network panel
+//! - Network panel with HAR export and response overrides
 //! - JavaScript debugger
-//! - Performance profiling
+//! - Performance profiling with trace-event export
 //! - Storage inspector
 //! - Application panel
 //! - Sources panel
 //! - Elements panel
 //! - Lighthouse audits
 //! - Memory panel
+//! - Coverage of CSS rules and JavaScript functions
+//! - Accessibility tree with audit findings and screen-reader order
+//! - Remote debugging over the Chrome DevTools Protocol
 
 pub mod console;
 pub mod inspector;
 pub mod network;
 pub mod debugger;
 pub mod performance;
+pub mod trace;
 pub mod storage;
 pub mod application;
 pub mod sources;
 pub mod elements;
 pub mod lighthouse;
 pub mod memory;
+pub mod coverage;
+pub mod accessibility;
 pub mod cdp;
+pub mod websocket;
+pub mod remote;
 
 pub use console::{Console, ConsoleMessage, ConsoleValue, LogLevel};
-pub use inspector::{Inspector, InspectedNode, NodeType};
-pub use network::{NetworkPanel, NetworkRequest, NetworkResponse, ResponsePreview, Cookie, NetworkThrottle};
+pub use inspector::{Inspector, InspectedNode, NodeType, DomEdit};
+pub use network::{NetworkPanel, NetworkRequest, NetworkResponse, ResponsePreview, Cookie, NetworkThrottle, TimingBreakdown, ResponseOverride};
 pub use debugger::{Debugger, Breakpoint, CallFrame, DebuggerState};
 pub use performance::{PerformancePanel, FrameTimingInfo, MemoryInfo, FlameChart, FlameChartNode, PaintEvent, ScriptExecutionEvent};
+pub use trace::{Tracer, TraceKind, TraceEvent, SpanId, ScriptLocation, FrameTimeline, TimelineFrame, TimeBreakdown};
 pub use storage::{StorageInspector, StoragePanel, StorageType, StorageEntry};
-pub use application::{ApplicationPanel, ServiceWorkerInfo, WebAppManifest, PwaStatus};
+pub use application::{ApplicationPanel, ServiceWorkerInfo, WebAppManifest, ManifestShortcut, ManifestIcon, PwaStatus};
 pub use sources::{SourcesPanel, SourceFile, SourceMap, JsPrettyPrinter};
 pub use elements::{ElementsPanel, ElementNode, ComputedStyles, BoxModel, MatchedRule};
 pub use lighthouse::{LighthousePanel, LighthouseReport, AuditResult, CategoryScore};
 pub use memory::{MemoryPanel, HeapSnapshot, HeapNode, AllocationSample};
+pub use coverage::{CoveragePanel, CoverageEntry, CoverageRange, CoverageType, UrlCoverage};
+pub use accessibility::{AccessibilityPanel, AxNode, AxIssue, AxIssueSeverity};
 pub use cdp::{CdpServer, CdpCommand, CdpResponse, CdpEvent, CdpError};
+pub use remote::CdpEndpoint;
 
 /// DevTools error
 #[derive(Debug, thiserror::Error)]
@@ -2,7 +2,7 @@
 //!
 //! Heap snapshots, allocation timeline, and memory leak detection.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Heap snapshot
 #[derive(Debug, Clone)]
@@ -27,7 +27,7 @@ pub struct HeapNode {
 }
 
 /// Heap node type
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum HeapNodeType { Hidden, Array, String, Object, Code, Closure, Regexp, Number, Native, Synthetic, Concatenated, Sliced, Symbol, BigInt }
 
 impl HeapNodeType {
@@ -112,11 +112,17 @@ impl MemoryPanel {
     
     /// Take heap snapshot
     pub fn take_snapshot(&mut self) -> u64 {
+        self.add_snapshot(Vec::new(), Vec::new())
+    }
+    
+    /// Store a snapshot captured from the JS heap
+    pub fn add_snapshot(&mut self, nodes: Vec<HeapNode>, edges: Vec<HeapEdge>) -> u64 {
         let id = self.next_snapshot_id;
         self.next_snapshot_id += 1;
         
-        let snapshot = HeapSnapshot { id, timestamp: current_time_ms(), total_size: 0,
-            node_count: 0, nodes: Vec::new(), edges: Vec::new() };
+        let total_size = nodes.iter().map(|n| n.size).sum();
+        let snapshot = HeapSnapshot { id, timestamp: current_time_ms(), total_size,
+            node_count: nodes.len(), nodes, edges };
         self.snapshots.push(snapshot);
         id
     }
@@ -135,6 +141,9 @@ impl MemoryPanel {
     /// Stop recording
     pub fn stop_recording(&mut self) { self.recording = false; }
     
+    /// Whether allocations are being recorded
+    pub fn is_recording(&self) -> bool { self.recording }
+    
     /// Record allocation
     pub fn record_allocation(&mut self, sample: AllocationSample) {
         if self.recording { self.allocations.push(sample); }
@@ -145,13 +154,52 @@ impl MemoryPanel {
         let before = self.get_snapshot(before_id);
         let after = self.get_snapshot(after_id);
         
-        let (size_delta, node_delta) = match (before, after) {
-            (Some(b), Some(a)) => (a.total_size as i64 - b.total_size as i64, a.node_count as i64 - b.node_count as i64),
-            _ => (0, 0),
+        let (Some(before), Some(after)) = (before, after) else {
+            return SnapshotComparison { before_id, after_id, size_delta: 0, node_delta: 0, added_nodes: Vec::new(),
+                deleted_nodes: Vec::new(), leak_candidates: Vec::new() };
         };
         
-        SnapshotComparison { before_id, after_id, size_delta, node_delta, added_nodes: Vec::new(),
-            deleted_nodes: Vec::new(), leak_candidates: Vec::new() }
+        let before_ids: HashSet<u64> = before.nodes.iter().map(|n| n.id).collect();
+        let after_ids: HashSet<u64> = after.nodes.iter().map(|n| n.id).collect();
+        let added_nodes: Vec<HeapNode> = after.nodes.iter().filter(|n| !before_ids.contains(&n.id)).cloned().collect();
+        let deleted_nodes: Vec<HeapNode> = before.nodes.iter().filter(|n| !after_ids.contains(&n.id)).cloned().collect();
+        
+        // Constructors whose instance count grew are the likely leaks
+        let mut groups: HashMap<(HeapNodeType, &str), (i64, i64)> = HashMap::new();
+        for node in &added_nodes {
+            let group = groups.entry((node.node_type, &node.name)).or_default();
+            group.0 += 1;
+            group.1 += node.size as i64;
+        }
+        for node in &deleted_nodes {
+            let group = groups.entry((node.node_type, &node.name)).or_default();
+            group.0 -= 1;
+            group.1 -= node.size as i64;
+        }
+        let mut leak_candidates: Vec<LeakCandidate> = groups.into_iter()
+            .filter(|(_, (count, _))| *count > 0)
+            .map(|((node_type, name), (count_delta, size_delta))| LeakCandidate {
+                node_type: node_type.as_str().to_string(),
+                constructor: name.to_string(),
+                count_delta,
+                size_delta,
+                allocation_site: self.allocation_site(&added_nodes, name),
+            })
+            .collect();
+        leak_candidates.sort_by(|a, b| b.size_delta.cmp(&a.size_delta).then_with(|| a.constructor.cmp(&b.constructor)));
+        
+        SnapshotComparison { before_id, after_id,
+            size_delta: after.total_size as i64 - before.total_size as i64,
+            node_delta: after.node_count as i64 - before.node_count as i64,
+            added_nodes, deleted_nodes, leak_candidates }
+    }
+    
+    /// Innermost frame of a recorded allocation of a new `constructor` instance
+    fn allocation_site(&self, added: &[HeapNode], constructor: &str) -> Option<StackFrame> {
+        added.iter()
+            .filter(|n| n.name == constructor)
+            .find_map(|n| self.allocations.iter().find(|a| a.node_id == n.id))
+            .and_then(|sample| sample.stack_trace.last().cloned())
     }
     
     /// Get allocation timeline
@@ -194,6 +242,28 @@ mod tests {
         assert_eq!(panel.get_snapshots().len(), 2);
     }
     
+    #[test]
+    fn test_compare_snapshots() {
+        let node = |id, name: &str, size| HeapNode { id, node_type: HeapNodeType::Object, name: name.into(),
+            size, retained_size: size, edge_count: 0 };
+        let mut panel = MemoryPanel::new();
+        panel.start_recording();
+        let frame = StackFrame { function_name: "addRow".into(), script_id: 0, url: String::new(), line: 0, column: 0 };
+        panel.record_allocation(AllocationSample { timestamp: 0, size: 64, node_id: 3, stack_trace: vec![frame] });
+        
+        let before = panel.add_snapshot(vec![node(1, "Row", 64), node(2, "Cell", 32)], Vec::new());
+        let after = panel.add_snapshot(vec![node(1, "Row", 64), node(3, "Row", 64)], Vec::new());
+        let diff = panel.compare_snapshots(before, after);
+        
+        assert_eq!(diff.size_delta, 32);
+        assert_eq!(diff.added_nodes.len(), 1);
+        assert_eq!(diff.deleted_nodes.len(), 1);
+        assert_eq!(diff.leak_candidates.len(), 1);
+        let leak = &diff.leak_candidates[0];
+        assert_eq!((leak.constructor.as_str(), leak.count_delta), ("Row", 1));
+        assert_eq!(leak.allocation_site.as_ref().unwrap().function_name, "addRow");
+    }
+    
     #[test]
     fn test_allocation_recording() {
         let mut panel = MemoryPanel::new();
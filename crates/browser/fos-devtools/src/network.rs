@@ -1,8 +1,11 @@
 //! Network Panel
 //!
-//! Request logging and inspection.
+//! Request logging and inspection: headers, bodies up to a size cap, a
+//! per-phase timing breakdown, HAR 1.2 export and local response overrides
+//! that the embedder serves instead of the network.
 
 use std::collections::HashMap;
+use crate::cdp::serde_value::{object, Value};
 
 /// Network request
 #[derive(Debug, Clone)]
@@ -12,8 +15,12 @@ pub struct NetworkRequest {
     pub method: String,
     pub request_headers: HashMap<String, String>,
     pub request_body: Option<Vec<u8>>,
+    /// Size of the request body before capping
+    pub request_body_size: usize,
     pub status: RequestStatus,
     pub timing: RequestTiming,
+    /// Time per phase, once known
+    pub phases: Option<TimingBreakdown>,
 }
 
 /// Request status
@@ -47,6 +54,35 @@ impl RequestTiming {
     }
 }
 
+/// Time spent in each phase of a request, in milliseconds; `None` for
+/// phases that did not happen, such as TLS on plain HTTP
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimingBreakdown {
+    /// Queued before a connection was available
+    pub blocked: Option<f64>,
+    pub dns: Option<f64>,
+    /// TCP connect, excluding TLS
+    pub connect: Option<f64>,
+    pub ssl: Option<f64>,
+    pub send: f64,
+    /// Waiting for the first byte
+    pub wait: f64,
+    pub receive: f64,
+}
+
+impl TimingBreakdown {
+    /// Sum of all phases
+    pub fn total(&self) -> f64 {
+        [self.blocked, self.dns, self.connect, self.ssl].iter().flatten().sum::<f64>()
+            + self.send + self.wait + self.receive
+    }
+    
+    /// Time to first byte
+    pub fn ttfb(&self) -> f64 {
+        self.total() - self.receive
+    }
+}
+
 /// Network response
 #[derive(Debug, Clone)]
 pub struct NetworkResponse {
@@ -55,10 +91,61 @@ pub struct NetworkResponse {
     pub status_text: String,
     pub headers: HashMap<String, String>,
     pub body: Option<Vec<u8>>,
+    /// Size of the body before capping
+    pub body_size: usize,
     pub content_type: Option<String>,
     pub content_length: Option<usize>,
 }
 
+impl NetworkResponse {
+    /// Whether the captured body was cut at the panel's body limit
+    pub fn is_body_truncated(&self) -> bool {
+        self.body.as_ref().is_some_and(|body| body.len() < self.body_size)
+    }
+}
+
+/// Local replacement for a response, served instead of the network
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseOverride {
+    /// URL to replace; a trailing `*` matches any rest
+    pub url_pattern: String,
+    pub status_code: u16,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl ResponseOverride {
+    /// Override answering 200 with `body`
+    pub fn new(url_pattern: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            url_pattern: url_pattern.to_string(),
+            status_code: 200,
+            headers: HashMap::new(),
+            body: body.into(),
+        }
+    }
+    
+    /// Set the status code
+    pub fn with_status(mut self, status_code: u16) -> Self {
+        self.status_code = status_code;
+        self
+    }
+    
+    /// Add a response header
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_ascii_lowercase(), value.to_string());
+        self
+    }
+    
+    /// Whether the override replaces `url`
+    pub fn matches(&self, url: &str) -> bool {
+        match self.url_pattern.strip_suffix('*') {
+            Some(prefix) => url.starts_with(prefix),
+            None => url == self.url_pattern,
+        }
+    }
+}
+
 /// Response preview type for DevTools display
 #[derive(Debug, Clone)]
 pub enum ResponsePreview {
@@ -148,7 +235,7 @@ impl ResponsePreview {
 }
 
 /// Simple base64 encoding
-fn base64_encode(data: &[u8]) -> String {
+pub(crate) fn base64_encode(data: &[u8]) -> String {
     const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
     let mut result = String::new();
     
@@ -365,8 +452,15 @@ pub struct NetworkPanel {
     throttle: Option<NetworkThrottle>,
     /// Cookies extracted from responses
     cookies: Vec<Cookie>,
+    /// Largest body kept per request or response
+    body_limit: usize,
+    /// Responses served locally instead of the network
+    overrides: Vec<ResponseOverride>,
 }
 
+/// Default cap on captured bodies
+pub const DEFAULT_BODY_LIMIT: usize = 10 * 1024 * 1024;
+
 impl Default for NetworkPanel {
     fn default() -> Self {
         Self {
@@ -377,6 +471,8 @@ impl Default for NetworkPanel {
             preserve_log: false,
             throttle: None,
             cookies: Vec::new(),
+            body_limit: DEFAULT_BODY_LIMIT,
+            overrides: Vec::new(),
         }
     }
 }
@@ -420,11 +516,13 @@ impl NetworkPanel {
             method: method.to_string(),
             request_headers: headers,
             request_body: None,
+            request_body_size: 0,
             status: RequestStatus::Pending,
             timing: RequestTiming {
                 start_time: current_time_ms(),
                 ..Default::default()
             },
+            phases: None,
         };
         
         self.requests.push(request);
@@ -447,6 +545,7 @@ impl NetworkPanel {
             status_text: status_text.to_string(),
             headers: headers.clone(),
             body: None,
+            body_size: 0,
             content_type: headers.get("content-type").cloned(),
             content_length: headers.get("content-length").and_then(|s| s.parse().ok()),
         };
@@ -501,13 +600,158 @@ impl NetworkPanel {
         }
     }
     
-    /// Set response body (for complete body capture)
-    pub fn set_response_body(&mut self, request_id: u64, body: Vec<u8>) {
+    /// Set response body, cut at the body limit
+    pub fn set_response_body(&mut self, request_id: u64, mut body: Vec<u8>) {
         if let Some(response) = self.responses.get_mut(&request_id) {
+            response.body_size = body.len();
+            body.truncate(self.body_limit);
             response.body = Some(body);
         }
     }
     
+    /// Set request body, such as POST data, cut at the body limit
+    pub fn set_request_body(&mut self, request_id: u64, body: &[u8]) {
+        if let Some(req) = self.requests.iter_mut().find(|r| r.id == request_id) {
+            req.request_body_size = body.len();
+            req.request_body = Some(body[..body.len().min(self.body_limit)].to_vec());
+        }
+    }
+    
+    /// Set the largest body kept per request or response
+    pub fn set_body_limit(&mut self, limit: usize) {
+        self.body_limit = limit;
+    }
+    
+    /// Record the time spent in each phase of a request
+    pub fn set_timing(&mut self, request_id: u64, phases: TimingBreakdown) {
+        if let Some(req) = self.requests.iter_mut().find(|r| r.id == request_id) {
+            req.phases = Some(phases);
+        }
+    }
+    
+    // === Overrides ===
+    
+    /// Serve `response` for matching URLs, replacing an override with the
+    /// same pattern
+    pub fn add_override(&mut self, response: ResponseOverride) {
+        self.overrides.retain(|o| o.url_pattern != response.url_pattern);
+        self.overrides.push(response);
+    }
+    
+    /// Remove the override for a pattern
+    pub fn remove_override(&mut self, url_pattern: &str) -> bool {
+        let before = self.overrides.len();
+        self.overrides.retain(|o| o.url_pattern != url_pattern);
+        self.overrides.len() != before
+    }
+    
+    /// All overrides
+    pub fn overrides(&self) -> &[ResponseOverride] {
+        &self.overrides
+    }
+    
+    /// Override for a URL; the longest matching pattern wins
+    pub fn find_override(&self, url: &str) -> Option<&ResponseOverride> {
+        self.overrides.iter()
+            .filter(|o| o.matches(url))
+            .max_by_key(|o| o.url_pattern.len())
+    }
+    
+    // === HAR ===
+    
+    /// Log as a HAR 1.2 archive
+    pub fn export_har(&self) -> String {
+        let entries = self.requests.iter().map(|r| self.har_entry(r)).collect();
+        object([("log", object([
+            ("version", "1.2".into()),
+            ("creator", object([("name", "fOS DevTools".into()), ("version", env!("CARGO_PKG_VERSION").into())])),
+            ("pages", Value::Seq(Vec::new())),
+            ("entries", Value::Seq(entries)),
+        ]))]).to_json()
+    }
+    
+    fn har_entry(&self, request: &NetworkRequest) -> Value {
+        let response = self.responses.get(&request.id);
+        let phases = request.phases.unwrap_or(TimingBreakdown {
+            wait: request.timing.total_time().unwrap_or(0) as f64,
+            ..Default::default()
+        });
+        let optional = |phase: Option<f64>| phase.map_or(Value::I64(-1), Value::from);
+        // HAR counts the TLS handshake inside `connect`
+        let connect = match (phases.connect, phases.ssl) {
+            (None, None) => None,
+            (connect, ssl) => Some(connect.unwrap_or(0.0) + ssl.unwrap_or(0.0)),
+        };
+        
+        let mut request_fields = vec![
+            ("method", request.method.as_str().into()),
+            ("url", request.url.as_str().into()),
+            ("httpVersion", "".into()),
+            ("cookies", har_request_cookies(&request.request_headers)),
+            ("headers", har_headers(&request.request_headers)),
+            ("queryString", har_query(&request.url)),
+            ("headersSize", Value::I64(-1)),
+            ("bodySize", (request.request_body_size as u64).into()),
+        ];
+        if let Some(body) = &request.request_body {
+            let mime_type = request.request_headers.get("content-type").map_or("", String::as_str);
+            request_fields.push(("postData", object([
+                ("mimeType", mime_type.into()),
+                ("text", String::from_utf8_lossy(body).into_owned().into()),
+            ])));
+        }
+        
+        let (status, status_text) = match &request.status {
+            RequestStatus::Complete { status_code, status_text } => (*status_code, status_text.as_str()),
+            _ => (0, ""),
+        };
+        let headers = response.map(|r| r.headers.clone()).unwrap_or_default();
+        let mut content = vec![
+            ("size", (response.map_or(0, |r| r.body_size.max(r.content_length.unwrap_or(0))) as u64).into()),
+            ("mimeType", response.and_then(|r| r.content_type.as_deref()).unwrap_or("x-unknown").into()),
+        ];
+        if let Some(body) = response.and_then(|r| r.body.as_ref()) {
+            match std::str::from_utf8(body) {
+                Ok(text) => content.push(("text", text.into())),
+                Err(_) => {
+                    content.push(("text", base64_encode(body).into()));
+                    content.push(("encoding", "base64".into()));
+                }
+            }
+        }
+        let mut response_fields = vec![
+            ("status", (status as u64).into()),
+            ("statusText", status_text.into()),
+            ("httpVersion", "".into()),
+            ("cookies", har_response_cookies(&headers, &request.url)),
+            ("headers", har_headers(&headers)),
+            ("content", object(content)),
+            ("redirectURL", headers.get("location").map_or("", String::as_str).into()),
+            ("headersSize", Value::I64(-1)),
+            ("bodySize", response.and_then(|r| r.content_length).map_or(Value::I64(-1), |n| (n as u64).into())),
+        ];
+        if let RequestStatus::Failed { error } = &request.status {
+            response_fields.push(("_error", error.as_str().into()));
+        }
+        
+        object([
+            ("startedDateTime", iso8601(request.timing.start_time).into()),
+            ("time", (phases.total() - phases.ssl.unwrap_or(0.0)).into()),
+            ("request", object(request_fields)),
+            ("response", object(response_fields)),
+            ("cache", object([])),
+            ("timings", object([
+                ("blocked", optional(phases.blocked)),
+                ("dns", optional(phases.dns)),
+                ("connect", optional(connect)),
+                ("ssl", optional(phases.ssl)),
+                ("send", phases.send.into()),
+                ("wait", phases.wait.into()),
+                ("receive", phases.receive.into()),
+            ])),
+        ])
+    }
+    
     // === Cookies ===
     
     /// Get all cookies
@@ -586,6 +830,71 @@ impl NetworkPanel {
     }
 }
 
+/// Name/value list of headers, sorted by name
+fn har_headers(headers: &HashMap<String, String>) -> Value {
+    let mut sorted: Vec<_> = headers.iter().collect();
+    sorted.sort();
+    Value::Seq(sorted.into_iter().map(|(name, value)| har_pair(name, value)).collect())
+}
+
+fn har_pair(name: &str, value: &str) -> Value {
+    object([("name", name.into()), ("value", value.into())])
+}
+
+/// Query parameters of a URL, not percent-decoded
+fn har_query(url: &str) -> Value {
+    let query = url.split('#').next().unwrap_or("").split_once('?').map_or("", |(_, q)| q);
+    Value::Seq(query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            har_pair(name, value)
+        })
+        .collect())
+}
+
+fn har_request_cookies(headers: &HashMap<String, String>) -> Value {
+    let header = headers.get("cookie").map_or("", String::as_str);
+    Value::Seq(header.split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .map(|(name, value)| har_pair(name, value))
+        .collect())
+}
+
+fn har_response_cookies(headers: &HashMap<String, String>, url: &str) -> Value {
+    let host = url.split("://").nth(1).unwrap_or("").split(['/', ':', '?']).next().unwrap_or("");
+    let cookie = headers.get("set-cookie").and_then(|header| Cookie::parse(header, host));
+    Value::Seq(cookie.into_iter().map(|c| object([
+        ("name", c.name.into()),
+        ("value", c.value.into()),
+        ("path", c.path.into()),
+        ("domain", c.domain.into()),
+        ("httpOnly", c.http_only.into()),
+        ("secure", c.secure.into()),
+    ])).collect())
+}
+
+/// UTC date and time of a Unix timestamp in milliseconds
+fn iso8601(ms: u64) -> String {
+    let days = (ms / 86_400_000) as i64;
+    let time = ms % 86_400_000;
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day,
+        time / 3_600_000, time / 60_000 % 60, time / 1000 % 60, time % 1000,
+    )
+}
+
 fn current_time_ms() -> u64 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -607,4 +916,57 @@ mod tests {
         assert_eq!(panel.requests.len(), 1);
         assert!(panel.get_response(id).is_some());
     }
+    
+    #[test]
+    fn test_bodies_timing_and_har() {
+        let mut panel = NetworkPanel::new();
+        panel.set_body_limit(4);
+        let mut headers = HashMap::new();
+        headers.insert("content-type".to_string(), "application/json".to_string());
+        let id = panel.log_request("https://example.com/api?q=1&page=2", "POST", headers.clone());
+        panel.set_request_body(id, b"{\"a\":1}");
+        headers.insert("set-cookie".to_string(), "session=abc; HttpOnly".to_string());
+        panel.log_response(id, 201, "Created", headers);
+        panel.set_response_body(id, b"{\"ok\":true}".to_vec());
+        panel.set_timing(id, TimingBreakdown {
+            dns: Some(5.0), connect: Some(10.0), ssl: Some(20.0),
+            send: 1.0, wait: 30.0, receive: 4.0, ..Default::default()
+        });
+        
+        let response = panel.get_response(id).unwrap();
+        assert_eq!(response.body.as_deref(), Some(&b"{\"ok"[..]));
+        assert!(response.is_body_truncated());
+        assert_eq!(panel.get_requests()[0].phases.unwrap().ttfb(), 66.0);
+        
+        let har = Value::from_json(&panel.export_har()).unwrap();
+        let entry = match har.get("log").and_then(|log| log.get("entries")) {
+            Some(Value::Seq(entries)) => entries[0].clone(),
+            _ => panic!("no entries"),
+        };
+        assert_eq!(entry.get("time").and_then(Value::as_f64), Some(50.0));
+        let timings = entry.get("timings").unwrap();
+        assert_eq!(timings.get("connect").and_then(Value::as_f64), Some(30.0));
+        assert_eq!(timings.get("blocked").and_then(Value::as_i64), Some(-1));
+        let request = entry.get("request").unwrap();
+        assert_eq!(request.get("postData").and_then(|p| p.get("text")).and_then(Value::as_str), Some("{\"a\""));
+        assert!(matches!(request.get("queryString"), Some(Value::Seq(q)) if q.len() == 2));
+        let response = entry.get("response").unwrap();
+        assert_eq!(response.get("status").and_then(Value::as_u64), Some(201));
+        assert!(matches!(response.get("cookies"), Some(Value::Seq(c)) if c.len() == 1));
+        assert!(entry.get("startedDateTime").and_then(Value::as_str).unwrap().ends_with('Z'));
+        assert_eq!(iso8601(951_782_400_123), "2000-02-29T00:00:00.123Z");
+    }
+    
+    #[test]
+    fn test_response_overrides() {
+        let mut panel = NetworkPanel::new();
+        panel.add_override(ResponseOverride::new("https://example.com/*", "site"));
+        panel.add_override(ResponseOverride::new("https://example.com/app.js", "app").with_status(203));
+        assert_eq!(panel.find_override("https://example.com/app.js").unwrap().status_code, 203);
+        assert_eq!(panel.find_override("https://example.com/index.html").unwrap().body, b"site");
+        assert!(panel.find_override("https://other.com/").is_none());
+        
+        assert!(panel.remove_override("https://example.com/*"));
+        assert!(panel.find_override("https://example.com/index.html").is_none());
+    }
 }
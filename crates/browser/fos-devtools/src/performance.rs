@@ -1,8 +1,10 @@
 //! Performance Panel
 //!
-//! Frame timing, CPU, and memory profiling.
+//! Frame timing, CPU, and memory profiling, with a trace-event timeline of
+//! the rendering pipeline (see [`crate::trace`]).
 
 use std::collections::VecDeque;
+use crate::trace::{FrameTimeline, Tracer};
 
 /// Performance entry
 #[derive(Debug, Clone)]
@@ -247,6 +249,8 @@ pub struct PerformancePanel {
     flame_chart: FlameChart,
     /// Next call UID for script events
     next_call_uid: u64,
+    /// Pipeline trace events of the recording
+    tracer: Tracer,
 }
 
 impl Default for PerformancePanel {
@@ -264,6 +268,7 @@ impl Default for PerformancePanel {
             script_events: Vec::new(),
             flame_chart: FlameChart::new(),
             next_call_uid: 0,
+            tracer: Tracer::new(),
         }
     }
 }
@@ -277,11 +282,18 @@ impl PerformancePanel {
         self.entries.clear();
         self.frames.clear();
         self.memory_samples.clear();
+        self.tracer.start();
     }
     
     /// Stop recording
     pub fn stop_recording(&mut self) {
         self.recording = false;
+        self.tracer.stop();
+    }
+    
+    /// Whether a recording is in progress
+    pub fn is_recording(&self) -> bool {
+        self.recording
     }
     
     /// Add performance mark
@@ -322,6 +334,7 @@ impl PerformancePanel {
     pub fn begin_frame(&mut self, frame_id: u64) {
         if self.recording {
             self.current_frame = Some(FrameTimingInfo::new(frame_id, current_time()));
+            self.tracer.begin_frame(frame_id);
         }
     }
     
@@ -355,6 +368,7 @@ impl PerformancePanel {
     
     /// End frame
     pub fn end_frame(&mut self) {
+        self.tracer.end_frame();
         if let Some(mut frame) = self.current_frame.take() {
             frame.total_time = current_time() - frame.start_time;
             
@@ -575,6 +589,27 @@ impl PerformancePanel {
         &self.flame_chart
     }
     
+    // === Trace Events ===
+    
+    /// Tracer the pipeline records into
+    pub fn tracer(&self) -> &Tracer {
+        &self.tracer
+    }
+    
+    pub fn tracer_mut(&mut self) -> &mut Tracer {
+        &mut self.tracer
+    }
+    
+    /// Frames of the recording with their time split by pipeline stage
+    pub fn frame_timeline(&self) -> FrameTimeline {
+        self.tracer.timeline()
+    }
+    
+    /// Recording as Chrome trace-event JSON, for Perfetto or chrome://tracing
+    pub fn export_trace(&self) -> String {
+        self.tracer.to_trace_json()
+    }
+    
     /// Clear all performance data
     pub fn clear_all(&mut self) {
         self.entries.clear();
@@ -585,6 +620,7 @@ impl PerformancePanel {
         self.paint_events.clear();
         self.script_events.clear();
         self.flame_chart = FlameChart::new();
+        self.tracer.clear();
     }
 }
 
@@ -0,0 +1,321 @@
+//! Remote Debugging Endpoint
+//!
+//! Serves a [`CdpServer`] over TCP the way Chromium's `--remote-debugging-port`
+//! does: `GET /json/version` and `GET /json/list` describe the browser and
+//! the inspected page, and a WebSocket upgrade on a target's
+//! `webSocketDebuggerUrl` carries protocol messages. The endpoint is
+//! non-blocking; the embedder calls [`CdpEndpoint::poll`] from its event
+//! loop, which also forwards events from the panels to every client.
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use crate::cdp::{CdpCommand, CdpResponse, CdpServer, PRODUCT, PROTOCOL_VERSION};
+use crate::cdp::serde_value::{object, Value};
+use crate::websocket::{accept_key, encode_frame, FrameDecoder, Message};
+
+/// WebSocket path of the inspected page
+const PAGE_PATH: &str = "/devtools/page/main";
+
+/// WebSocket path of the browser target
+const BROWSER_PATH: &str = "/devtools/browser/fos";
+
+/// Largest HTTP request head accepted
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// Remote debugging endpoint
+#[derive(Debug)]
+pub struct CdpEndpoint {
+    listener: TcpListener,
+    clients: Vec<Client>,
+}
+
+#[derive(Debug)]
+struct Client {
+    stream: TcpStream,
+    /// Bytes received and not yet consumed
+    incoming: Vec<u8>,
+    /// Bytes not yet written to the socket
+    outgoing: Vec<u8>,
+    /// Set once the WebSocket handshake completed
+    decoder: Option<FrameDecoder>,
+    /// Close once `outgoing` is flushed
+    closing: bool,
+}
+
+impl CdpEndpoint {
+    /// Listen on an address, e.g. `127.0.0.1:9222`
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            clients: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Number of connected clients
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Accept connections, answer requests and commands, and send pending
+    /// events. Never blocks.
+    pub fn poll(&mut self, server: &mut CdpServer) -> io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    self.clients.push(Client {
+                        stream,
+                        incoming: Vec::new(),
+                        outgoing: Vec::new(),
+                        decoder: None,
+                        closing: false,
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let addr = self.local_addr()?;
+        for client in &mut self.clients {
+            if client.read().is_err() {
+                client.closing = true;
+                client.outgoing.clear();
+                continue;
+            }
+            if client.decoder.is_none() {
+                client.handle_http(addr, server);
+            }
+            if client.decoder.is_some() {
+                client.handle_messages(server);
+            }
+        }
+
+        server.collect_events();
+        let events = server.take_events();
+        for client in self.clients.iter_mut().filter(|c| c.decoder.is_some() && !c.closing) {
+            for event in &events {
+                client.send(Message::Text(event.to_json()));
+            }
+        }
+
+        self.clients.retain_mut(|client| client.flush().is_ok() && !(client.closing && client.outgoing.is_empty()));
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Read what is available; errors when the peer went away
+    fn read(&mut self) -> io::Result<()> {
+        let mut buf = [0u8; 8192];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(n) => {
+                    match &mut self.decoder {
+                        Some(decoder) => decoder.push(&buf[..n]),
+                        None => self.incoming.extend_from_slice(&buf[..n]),
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.outgoing.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, message: Message) {
+        self.outgoing.extend(encode_frame(&message));
+    }
+
+    /// Answer the HTTP request once its head arrived
+    fn handle_http(&mut self, addr: SocketAddr, server: &CdpServer) {
+        if self.closing {
+            return;
+        }
+        let Some(end) = self.incoming.windows(4).position(|w| w == b"\r\n\r\n") else {
+            if self.incoming.len() > MAX_REQUEST_HEAD {
+                self.respond(431, "Request Header Fields Too Large", "text/plain", "");
+            }
+            return;
+        };
+        let head = String::from_utf8_lossy(&self.incoming[..end]).into_owned();
+        let rest = self.incoming.split_off(end + 4);
+        self.incoming.clear();
+
+        let mut lines = head.split("\r\n");
+        let mut request_line = lines.next().unwrap_or("").split(' ');
+        let (method, path) = (request_line.next().unwrap_or(""), request_line.next().unwrap_or(""));
+        let header = |name: &str| head.split("\r\n").skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().to_string());
+        let path = path.split('?').next().unwrap_or("");
+
+        if method != "GET" {
+            self.respond(405, "Method Not Allowed", "text/plain", "");
+            return;
+        }
+        if path == PAGE_PATH || path == BROWSER_PATH {
+            let upgrade = header("Upgrade").is_some_and(|u| u.eq_ignore_ascii_case("websocket"));
+            match header("Sec-WebSocket-Key").filter(|_| upgrade) {
+                Some(key) => {
+                    self.outgoing.extend_from_slice(format!(
+                        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                        accept_key(&key),
+                    ).as_bytes());
+                    let mut decoder = FrameDecoder::new();
+                    decoder.push(&rest);
+                    self.decoder = Some(decoder);
+                }
+                None => self.respond(400, "Bad Request", "text/plain", "WebSocket upgrade expected"),
+            }
+            return;
+        }
+
+        let host = header("Host").unwrap_or_else(|| addr.to_string());
+        let body = match path.trim_end_matches('/') {
+            "/json/version" => object([
+                ("Browser", PRODUCT.into()),
+                ("Protocol-Version", PROTOCOL_VERSION.into()),
+                ("User-Agent", PRODUCT.into()),
+                ("webSocketDebuggerUrl", format!("ws://{}{}", host, BROWSER_PATH).into()),
+            ]),
+            "/json" | "/json/list" => Value::Seq(vec![object([
+                ("id", "main".into()),
+                ("type", "page".into()),
+                ("title", server.page_title().into()),
+                ("url", server.page_url().into()),
+                ("description", "".into()),
+                ("devtoolsFrontendUrl", format!("devtools://devtools/bundled/inspector.html?ws={}{}", host, PAGE_PATH).into()),
+                ("webSocketDebuggerUrl", format!("ws://{}{}", host, PAGE_PATH).into()),
+            ])]),
+            _ => {
+                self.respond(404, "Not Found", "text/plain", "");
+                return;
+            }
+        };
+        self.respond(200, "OK", "application/json; charset=UTF-8", &body.to_json());
+    }
+
+    /// Send a response and close the connection
+    fn respond(&mut self, status: u16, reason: &str, content_type: &str, body: &str) {
+        self.outgoing.extend_from_slice(format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, reason, content_type, body.len(), body,
+        ).as_bytes());
+        self.closing = true;
+    }
+
+    fn handle_messages(&mut self, server: &mut CdpServer) {
+        while !self.closing {
+            let Some(decoder) = &mut self.decoder else { return };
+            let message = match decoder.next_message() {
+                Ok(Some(message)) => message,
+                Ok(None) => return,
+                Err(_) => {
+                    // Protocol error
+                    self.send(Message::Close(Some(1002)));
+                    self.closing = true;
+                    return;
+                }
+            };
+            match message {
+                Message::Text(text) => {
+                    let response = match CdpCommand::from_json(&text) {
+                        Ok(cmd) => server.handle_command(cmd),
+                        // The id is unknown when the message is malformed
+                        Err(error) => CdpResponse::error(0, error),
+                    };
+                    self.send(Message::Text(response.to_json()));
+                }
+                Message::Binary(_) => {
+                    // Unsupported data
+                    self.send(Message::Close(Some(1003)));
+                    self.closing = true;
+                }
+                Message::Ping(data) => self.send(Message::Pong(data)),
+                Message::Pong(_) => {}
+                Message::Close(_) => {
+                    self.send(Message::Close(Some(1000)));
+                    self.closing = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Poll until the client has read `until` from the endpoint
+    fn exchange(endpoint: &mut CdpEndpoint, server: &mut CdpServer, client: &mut TcpStream, until: &[u8]) -> Vec<u8> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut received = Vec::new();
+        let mut buf = [0u8; 4096];
+        while !received.windows(until.len()).any(|w| w == until) {
+            assert!(Instant::now() < deadline, "timed out: {:?}", String::from_utf8_lossy(&received));
+            endpoint.poll(server).unwrap();
+            match client.read(&mut buf) {
+                Ok(n) => received.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(Duration::from_millis(1)),
+                Err(e) => panic!("{}", e),
+            }
+        }
+        received
+    }
+
+    #[test]
+    fn test_handshake_and_command() {
+        let mut server = CdpServer::new();
+        server.set_page("https://example.com/", "Example");
+        let mut endpoint = CdpEndpoint::bind("127.0.0.1:0").unwrap();
+        let addr = endpoint.local_addr().unwrap();
+
+        let mut http = TcpStream::connect(addr).unwrap();
+        http.set_nonblocking(true).unwrap();
+        http.write_all(b"GET /json/list HTTP/1.1\r\nHost: localhost:9222\r\n\r\n").unwrap();
+        let listing = exchange(&mut endpoint, &mut server, &mut http, b"]");
+        assert!(String::from_utf8_lossy(&listing).contains("\"webSocketDebuggerUrl\":\"ws://localhost:9222/devtools/page/main\""));
+
+        let mut ws = TcpStream::connect(addr).unwrap();
+        ws.set_nonblocking(true).unwrap();
+        ws.write_all(b"GET /devtools/page/main HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n").unwrap();
+        let command = br#"{"id":1,"method":"Browser.getVersion"}"#;
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![0x81, 0x80 | command.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(command.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        ws.write_all(&frame).unwrap();
+
+        let reply = exchange(&mut endpoint, &mut server, &mut ws, b"protocolVersion");
+        let reply = String::from_utf8_lossy(&reply);
+        assert!(reply.starts_with("HTTP/1.1 101") && reply.contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+        assert!(reply.contains("\"id\":1"));
+        assert_eq!(endpoint.client_count(), 1);
+    }
+}
@@ -0,0 +1,444 @@
+//! Trace Events
+//!
+//! Scoped trace events recorded by the rendering pipeline (style recalc,
+//! layout, paint, composite) and script tasks, grouped into frames. The
+//! recording is aggregated into a [`FrameTimeline`] for the Performance
+//! panel and exported as Chrome trace-event JSON for chrome://tracing and
+//! Perfetto.
+
+use std::sync::OnceLock;
+use std::time::Instant;
+use crate::cdp::serde_value::{object, Value};
+use crate::performance::ScriptEventType;
+
+/// Category of the DevTools timeline events
+const TIMELINE_CATEGORY: &str = "devtools.timeline";
+
+/// Frame budget at 60 Hz
+pub const FRAME_BUDGET_MS: f64 = 1000.0 / 60.0;
+
+/// Microseconds on the trace clock, shared by every tracer in the process
+pub fn trace_time() -> f64 {
+    static ORIGIN: OnceLock<Instant> = OnceLock::new();
+    ORIGIN.get_or_init(Instant::now).elapsed().as_secs_f64() * 1_000_000.0
+}
+
+/// Script location on top of the stack when a task started
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptLocation {
+    pub function_name: String,
+    pub url: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// What a trace event measures
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceKind {
+    /// Style recalculation
+    Style,
+    Layout,
+    Paint,
+    /// Compositing layers and presenting the frame
+    Composite,
+    /// JavaScript task
+    Script { task: ScriptEventType, stack_top: Option<ScriptLocation> },
+    /// Anything else worth a slice, e.g. HTML parsing
+    Other(String),
+}
+
+impl TraceKind {
+    /// Event name used by the Chrome timeline
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Style => "UpdateLayoutTree",
+            Self::Layout => "Layout",
+            Self::Paint => "Paint",
+            Self::Composite => "CompositeLayers",
+            Self::Script { task, .. } => match task {
+                ScriptEventType::FunctionCall => "FunctionCall",
+                ScriptEventType::Compile => "v8.compile",
+                ScriptEventType::Execute | ScriptEventType::EvaluateScript => "EvaluateScript",
+                ScriptEventType::GC => "MinorGC",
+                ScriptEventType::ParseHTML => "ParseHTML",
+                ScriptEventType::EventHandler => "EventDispatch",
+                ScriptEventType::TimerFire => "TimerFire",
+                ScriptEventType::RequestAnimationFrame => "FireAnimationFrame",
+            },
+            Self::Other(name) => name,
+        }
+    }
+}
+
+/// One recorded slice
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    pub kind: TraceKind,
+    /// Start on the trace clock (µs)
+    pub start: f64,
+    /// Duration (µs); zero while the slice is open
+    pub duration: f64,
+    /// Nesting depth within other open slices
+    pub depth: u32,
+    /// Frame the slice started in
+    pub frame: Option<u64>,
+}
+
+impl TraceEvent {
+    pub fn end(&self) -> f64 {
+        self.start + self.duration
+    }
+}
+
+/// Frame boundaries on the trace clock (µs)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceFrame {
+    pub id: u64,
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+/// Handle of an open slice, closed with [`Tracer::end`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use]
+pub struct SpanId(Option<usize>);
+
+/// Records trace events while recording is on; otherwise every call is a
+/// cheap no-op so the pipeline can stay instrumented
+#[derive(Debug, Default)]
+pub struct Tracer {
+    recording: bool,
+    events: Vec<TraceEvent>,
+    /// Indices of open slices, innermost last
+    open: Vec<usize>,
+    frames: Vec<TraceFrame>,
+}
+
+impl Tracer {
+    pub fn new() -> Self { Self::default() }
+    
+    /// Start a new recording
+    pub fn start(&mut self) {
+        self.clear();
+        self.recording = true;
+    }
+    
+    /// Stop recording, keeping what was recorded
+    pub fn stop(&mut self) {
+        self.recording = false;
+    }
+    
+    pub fn is_recording(&self) -> bool { self.recording }
+    
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.open.clear();
+        self.frames.clear();
+    }
+    
+    /// Open a slice now
+    pub fn begin(&mut self, kind: TraceKind) -> SpanId {
+        if !self.recording { return SpanId(None); }
+        self.begin_at(kind, trace_time())
+    }
+    
+    /// Open a slice at a trace-clock time
+    pub fn begin_at(&mut self, kind: TraceKind, start: f64) -> SpanId {
+        if !self.recording { return SpanId(None); }
+        let frame = self.frames.last().filter(|f| f.end.is_none()).map(|f| f.id);
+        self.events.push(TraceEvent { kind, start, duration: 0.0, depth: self.open.len() as u32, frame });
+        self.open.push(self.events.len() - 1);
+        SpanId(Some(self.events.len() - 1))
+    }
+    
+    /// Close a slice now
+    pub fn end(&mut self, span: SpanId) {
+        if span.0.is_some() {
+            self.end_at(span, trace_time());
+        }
+    }
+    
+    /// Close a slice, and any slice opened inside it, at a trace-clock time
+    pub fn end_at(&mut self, span: SpanId, end: f64) {
+        let Some(index) = span.0 else { return };
+        let Some(pos) = self.open.iter().position(|&i| i == index) else { return };
+        for i in self.open.drain(pos..) {
+            let event = &mut self.events[i];
+            event.duration = (end - event.start).max(0.0);
+        }
+    }
+    
+    /// Run `f` inside a slice
+    pub fn scope<R>(&mut self, kind: TraceKind, f: impl FnOnce(&mut Self) -> R) -> R {
+        let span = self.begin(kind);
+        let result = f(self);
+        self.end(span);
+        result
+    }
+    
+    /// Start a frame now
+    pub fn begin_frame(&mut self, id: u64) {
+        if self.recording {
+            self.begin_frame_at(id, trace_time());
+        }
+    }
+    
+    pub fn begin_frame_at(&mut self, id: u64, start: f64) {
+        if !self.recording { return; }
+        self.end_frame_at(start);
+        self.frames.push(TraceFrame { id, start, end: None });
+    }
+    
+    /// End the current frame now
+    pub fn end_frame(&mut self) {
+        if self.recording {
+            self.end_frame_at(trace_time());
+        }
+    }
+    
+    pub fn end_frame_at(&mut self, end: f64) {
+        if let Some(frame) = self.frames.last_mut().filter(|f| f.end.is_none()) {
+            frame.end = Some(end.max(frame.start));
+        }
+    }
+    
+    pub fn events(&self) -> &[TraceEvent] { &self.events }
+    
+    pub fn frames(&self) -> &[TraceFrame] { &self.frames }
+    
+    /// Per-frame attribution of the recorded time
+    pub fn timeline(&self) -> FrameTimeline {
+        // Self time of each slice: its duration minus its direct children
+        let mut self_time: Vec<f64> = self.events.iter().map(|e| e.duration).collect();
+        let mut stack: Vec<usize> = Vec::new();
+        for (i, event) in self.events.iter().enumerate() {
+            while stack.last().is_some_and(|&p| self.events[p].depth >= event.depth) {
+                stack.pop();
+            }
+            if let Some(&parent) = stack.last() {
+                self_time[parent] -= event.duration;
+            }
+            stack.push(i);
+        }
+        
+        let frames = self.frames.iter().filter_map(|frame| {
+            let end = frame.end?;
+            let mut timeline_frame = TimelineFrame {
+                id: frame.id,
+                start: frame.start / 1000.0,
+                duration: (end - frame.start) / 1000.0,
+                breakdown: TimeBreakdown::default(),
+                busiest_script: None,
+            };
+            let mut busiest = 0.0;
+            for (event, &time) in self.events.iter().zip(&self_time) {
+                if event.frame != Some(frame.id) { continue; }
+                let ms = time.max(0.0) / 1000.0;
+                timeline_frame.breakdown.add(&event.kind, ms);
+                if let TraceKind::Script { stack_top: Some(location), .. } = &event.kind {
+                    if ms > busiest {
+                        busiest = ms;
+                        timeline_frame.busiest_script = Some(location.clone());
+                    }
+                }
+            }
+            Some(timeline_frame)
+        }).collect();
+        FrameTimeline { frames }
+    }
+    
+    /// Recording as Chrome trace-event JSON (`{"traceEvents": [...]}`)
+    pub fn to_trace_json(&self) -> String {
+        object([
+            ("traceEvents", Value::Seq(self.trace_events())),
+            ("displayTimeUnit", "ms".into()),
+        ]).to_json()
+    }
+    
+    /// Recording as trace-event objects, as sent by `Tracing.dataCollected`
+    pub fn trace_events(&self) -> Vec<Value> {
+        let pid = std::process::id() as u64;
+        let common = |name: &str, ph: &str, ts: f64| vec![
+            ("name", Value::from(name)),
+            ("cat", TIMELINE_CATEGORY.into()),
+            ("ph", ph.into()),
+            ("ts", ts.into()),
+            ("pid", pid.into()),
+            ("tid", 1u64.into()),
+        ];
+        let mut out = vec![
+            object([("name", "process_name".into()), ("ph", "M".into()), ("pid", pid.into()), ("tid", 1u64.into()),
+                ("args", object([("name", "fOS".into())]))]),
+            object([("name", "thread_name".into()), ("ph", "M".into()), ("pid", pid.into()), ("tid", 1u64.into()),
+                ("args", object([("name", "CrRendererMain".into())]))]),
+        ];
+        for frame in &self.frames {
+            let mut begin = common("BeginFrame", "I", frame.start);
+            begin.push(("s", "t".into()));
+            begin.push(("args", object([("frameSeqId", frame.id.into())])));
+            out.push(object(begin));
+            if let Some(end) = frame.end {
+                let mut draw = common("DrawFrame", "I", end);
+                draw.push(("s", "t".into()));
+                draw.push(("args", object([("frameSeqId", frame.id.into())])));
+                out.push(object(draw));
+            }
+        }
+        for event in &self.events {
+            let mut fields = common(event.kind.name(), "X", event.start);
+            fields.push(("dur", event.duration.into()));
+            if let TraceKind::Script { stack_top: Some(location), .. } = &event.kind {
+                fields.push(("args", object([("data", object([
+                    ("functionName", location.function_name.as_str().into()),
+                    ("url", location.url.as_str().into()),
+                    ("lineNumber", (location.line as u64).into()),
+                    ("columnNumber", (location.column as u64).into()),
+                ]))])));
+            }
+            out.push(object(fields));
+        }
+        out
+    }
+}
+
+/// Self time (ms) by pipeline stage
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeBreakdown {
+    pub script: f64,
+    pub style: f64,
+    pub layout: f64,
+    pub paint: f64,
+    pub composite: f64,
+    pub other: f64,
+}
+
+impl TimeBreakdown {
+    fn add(&mut self, kind: &TraceKind, ms: f64) {
+        match kind {
+            TraceKind::Style => self.style += ms,
+            TraceKind::Layout => self.layout += ms,
+            TraceKind::Paint => self.paint += ms,
+            TraceKind::Composite => self.composite += ms,
+            TraceKind::Script { .. } => self.script += ms,
+            TraceKind::Other(_) => self.other += ms,
+        }
+    }
+    
+    pub fn total(&self) -> f64 {
+        self.script + self.style + self.layout + self.paint + self.composite + self.other
+    }
+}
+
+/// One frame of the timeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineFrame {
+    pub id: u64,
+    /// Start (ms on the trace clock)
+    pub start: f64,
+    /// Wall time of the frame (ms)
+    pub duration: f64,
+    pub breakdown: TimeBreakdown,
+    /// Script with the most self time in the frame
+    pub busiest_script: Option<ScriptLocation>,
+}
+
+impl TimelineFrame {
+    /// Frame took longer than the budget
+    pub fn is_long(&self, budget_ms: f64) -> bool {
+        self.duration > budget_ms
+    }
+    
+    /// Time in the frame not spent in any recorded slice (ms)
+    pub fn idle(&self) -> f64 {
+        (self.duration - self.breakdown.total()).max(0.0)
+    }
+}
+
+/// Frames of a recording with their time attribution
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameTimeline {
+    pub frames: Vec<TimelineFrame>,
+}
+
+impl FrameTimeline {
+    /// Frames over the 60 Hz budget
+    pub fn long_frames(&self) -> impl Iterator<Item = &TimelineFrame> {
+        self.frames.iter().filter(|f| f.is_long(FRAME_BUDGET_MS))
+    }
+    
+    /// Breakdown summed over all frames
+    pub fn totals(&self) -> TimeBreakdown {
+        self.frames.iter().fold(TimeBreakdown::default(), |mut sum, f| {
+            sum.script += f.breakdown.script;
+            sum.style += f.breakdown.style;
+            sum.layout += f.breakdown.layout;
+            sum.paint += f.breakdown.paint;
+            sum.composite += f.breakdown.composite;
+            sum.other += f.breakdown.other;
+            sum
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn script(function_name: &str) -> TraceKind {
+        TraceKind::Script {
+            task: ScriptEventType::TimerFire,
+            stack_top: Some(ScriptLocation { function_name: function_name.into(), url: "app.js".into(), line: 3, column: 1 }),
+        }
+    }
+    
+    #[test]
+    fn test_frame_attribution() {
+        let mut tracer = Tracer::new();
+        let ignored = tracer.begin(TraceKind::Layout);
+        assert_eq!(ignored, SpanId(None));
+        
+        tracer.start();
+        tracer.begin_frame_at(1, 0.0);
+        // 8 ms timer task forcing a 3 ms layout
+        let task = tracer.begin_at(script("tick"), 1000.0);
+        let forced = tracer.begin_at(TraceKind::Layout, 4000.0);
+        tracer.end_at(forced, 7000.0);
+        tracer.end_at(task, 9000.0);
+        for (kind, start) in [(TraceKind::Style, 9000.0), (TraceKind::Layout, 11000.0), (TraceKind::Paint, 13000.0), (TraceKind::Composite, 17000.0)] {
+            let span = tracer.begin_at(kind, start);
+            tracer.end_at(span, start + 2000.0);
+        }
+        tracer.begin_frame_at(2, 20000.0);
+        let span = tracer.begin_at(TraceKind::Paint, 20000.0);
+        tracer.end_at(span, 21000.0);
+        tracer.end_frame_at(30000.0);
+        
+        let timeline = tracer.timeline();
+        let first = &timeline.frames[0];
+        assert_eq!(first.duration, 20.0);
+        assert_eq!(first.breakdown, TimeBreakdown { script: 5.0, style: 2.0, layout: 5.0, paint: 2.0, composite: 2.0, other: 0.0 });
+        assert_eq!(first.busiest_script.as_ref().map(|l| l.function_name.as_str()), Some("tick"));
+        assert_eq!(first.idle(), 4.0);
+        assert_eq!(timeline.long_frames().map(|f| f.id).collect::<Vec<_>>(), [1]);
+        assert_eq!(timeline.totals().paint, 3.0);
+    }
+    
+    #[test]
+    fn test_trace_event_json() {
+        let mut tracer = Tracer::new();
+        tracer.start();
+        tracer.begin_frame_at(7, 100.0);
+        let span = tracer.begin_at(script("onClick"), 150.0);
+        tracer.end_at(span, 400.0);
+        tracer.end_frame_at(500.0);
+        
+        let json = Value::from_json(&tracer.to_trace_json()).unwrap();
+        let events = match json.get("traceEvents") { Some(Value::Seq(events)) => events.clone(), _ => panic!("no traceEvents") };
+        let task = events.iter().find(|e| e.get("ph").and_then(Value::as_str) == Some("X")).unwrap();
+        assert_eq!(task.get("name").and_then(Value::as_str), Some("TimerFire"));
+        assert_eq!(task.get("dur").and_then(Value::as_f64), Some(250.0));
+        let data = task.get("args").and_then(|a| a.get("data")).unwrap();
+        assert_eq!(data.get("functionName").and_then(Value::as_str), Some("onClick"));
+        assert!(events.iter().any(|e| e.get("name").and_then(Value::as_str) == Some("DrawFrame")));
+    }
+}
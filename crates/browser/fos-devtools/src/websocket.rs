@@ -0,0 +1,258 @@
+//! WebSocket Framing
+//!
+//! The parts of RFC 6455 the remote debugging endpoint needs: the opening
+//! handshake's accept key, and message framing in both directions. Client
+//! frames must be masked, fragmented messages are reassembled, and messages
+//! larger than a limit are rejected before they are buffered.
+
+use crate::network::base64_encode;
+
+/// GUID appended to the client key in the opening handshake
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest message accepted from a client
+pub const MAX_MESSAGE_SIZE: usize = 16 << 20;
+
+/// WebSocket message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    /// Close, with the status code if one was sent
+    Close(Option<u16>),
+}
+
+/// Framing error; the connection must be closed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FrameError {
+    #[error("Client frame is not masked")]
+    Unmasked,
+
+    #[error("Invalid control frame")]
+    InvalidControlFrame,
+
+    #[error("Message exceeds {MAX_MESSAGE_SIZE} bytes")]
+    MessageTooLarge,
+
+    #[error("Text message is not valid UTF-8")]
+    InvalidUtf8,
+
+    #[error("Unknown opcode {0:#x}")]
+    UnknownOpcode(u8),
+
+    #[error("Continuation frame without a message")]
+    UnexpectedContinuation,
+}
+
+/// `Sec-WebSocket-Accept` value for a client's `Sec-WebSocket-Key`
+pub fn accept_key(key: &str) -> String {
+    base64_encode(&sha1(format!("{}{}", key.trim(), HANDSHAKE_GUID).as_bytes()))
+}
+
+/// Reassembles client messages from a byte stream
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    /// Opcode and payload of a fragmented message in progress
+    fragments: Option<(u8, Vec<u8>)>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self { Self::default() }
+
+    /// Buffer bytes read from the connection
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Next complete message, or `None` until more data arrives
+    pub fn next_message(&mut self) -> Result<Option<Message>, FrameError> {
+        loop {
+            let Some((fin, opcode, payload)) = self.next_frame()? else { return Ok(None) };
+            let message = match opcode {
+                0x0 => {
+                    let Some((_, data)) = self.fragments.as_mut() else {
+                        return Err(FrameError::UnexpectedContinuation);
+                    };
+                    if data.len() + payload.len() > MAX_MESSAGE_SIZE {
+                        return Err(FrameError::MessageTooLarge);
+                    }
+                    data.extend_from_slice(&payload);
+                    if !fin {
+                        continue;
+                    }
+                    let (opcode, data) = self.fragments.take().unwrap_or_default();
+                    data_message(opcode, data)?
+                }
+                0x1 | 0x2 if !fin => {
+                    self.fragments = Some((opcode, payload));
+                    continue;
+                }
+                0x1 | 0x2 => data_message(opcode, payload)?,
+                0x8 => Message::Close((payload.len() >= 2).then(|| u16::from_be_bytes([payload[0], payload[1]]))),
+                0x9 => Message::Ping(payload),
+                0xA => Message::Pong(payload),
+                other => return Err(FrameError::UnknownOpcode(other)),
+            };
+            return Ok(Some(message));
+        }
+    }
+
+    /// (fin, opcode, unmasked payload) of the next complete frame
+    fn next_frame(&mut self) -> Result<Option<(bool, u8, Vec<u8>)>, FrameError> {
+        let buf = &self.buffer;
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        let fin = buf[0] & 0x80 != 0;
+        let opcode = buf[0] & 0x0F;
+        if buf[1] & 0x80 == 0 {
+            return Err(FrameError::Unmasked);
+        }
+        let (len, mut pos) = match buf[1] & 0x7F {
+            126 if buf.len() >= 4 => (u16::from_be_bytes([buf[2], buf[3]]) as u64, 4),
+            127 if buf.len() >= 10 => (u64::from_be_bytes(buf[2..10].try_into().unwrap_or_default()), 10),
+            126 | 127 => return Ok(None),
+            len => (len as u64, 2),
+        };
+        if opcode & 0x08 != 0 && (!fin || len > 125) {
+            return Err(FrameError::InvalidControlFrame);
+        }
+        if len > MAX_MESSAGE_SIZE as u64 {
+            return Err(FrameError::MessageTooLarge);
+        }
+        let len = len as usize;
+        if buf.len() < pos + 4 + len {
+            return Ok(None);
+        }
+        let mask = [buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]];
+        pos += 4;
+        let payload = buf[pos..pos + len].iter().enumerate().map(|(i, b)| b ^ mask[i % 4]).collect();
+        self.buffer.drain(..pos + len);
+        Ok(Some((fin, opcode, payload)))
+    }
+}
+
+fn data_message(opcode: u8, payload: Vec<u8>) -> Result<Message, FrameError> {
+    if opcode == 0x1 {
+        String::from_utf8(payload).map(Message::Text).map_err(|_| FrameError::InvalidUtf8)
+    } else {
+        Ok(Message::Binary(payload))
+    }
+}
+
+/// Unmasked server frame carrying a whole message
+pub fn encode_frame(message: &Message) -> Vec<u8> {
+    let close_payload;
+    let (opcode, payload): (u8, &[u8]) = match message {
+        Message::Text(text) => (0x1, text.as_bytes()),
+        Message::Binary(data) => (0x2, data),
+        Message::Close(code) => {
+            close_payload = code.map(u16::to_be_bytes);
+            (0x8, close_payload.as_ref().map_or(&[][..], |c| &c[..]))
+        }
+        Message::Ping(data) => (0x9, data),
+        Message::Pong(data) => (0xA, data),
+    };
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// SHA-1 (FIPS 180-4), only used for the handshake
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (chunk, word) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn masked(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x37, 0xFA, 0x21, 0x3D];
+        let mut frame = vec![first, 0x80 | payload.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // RFC 6455 §1.3
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn test_fragmented_message() {
+        let mut decoder = FrameDecoder::new();
+        let mut bytes = masked(0x01, b"Hel");
+        bytes.extend(masked(0x89, b"p"));
+        bytes.extend(masked(0x80, b"lo"));
+        decoder.push(&bytes[..5]);
+        assert_eq!(decoder.next_message(), Ok(None));
+        decoder.push(&bytes[5..]);
+        assert_eq!(decoder.next_message(), Ok(Some(Message::Ping(b"p".to_vec()))));
+        assert_eq!(decoder.next_message(), Ok(Some(Message::Text("Hello".into()))));
+        assert_eq!(decoder.next_message(), Ok(None));
+
+        assert_eq!(encode_frame(&Message::Text("Hi".into())), vec![0x81, 2, b'H', b'i']);
+        assert_eq!(encode_frame(&Message::Binary(vec![0; 200]))[..4], [0x82, 126, 0, 200]);
+
+        let mut unmasked = FrameDecoder::new();
+        unmasked.push(&[0x81, 0x02, b'H', b'i']);
+        assert_eq!(unmasked.next_message(), Err(FrameError::Unmasked));
+    }
+}
@@ -0,0 +1,252 @@
+//! Analyser Node
+//!
+//! Passes audio through unchanged while keeping the most recent samples for
+//! time-domain snapshots and FFT magnitude spectra (Blackman window with
+//! smoothing over time, as specified by Web Audio).
+
+use super::graph::{AudioBlock, AudioProcessor, RenderQuantum};
+use std::f64::consts::PI;
+
+/// Largest supported FFT size
+const MAX_FFT_SIZE: usize = 32768;
+
+/// Analyser node
+#[derive(Debug)]
+pub struct AnalyserNode {
+    pub id: u32,
+    pub fft_size: usize,
+    pub min_decibels: f64,
+    pub max_decibels: f64,
+    pub smoothing_time_constant: f64,
+    /// Ring buffer of the latest mono-mixed input
+    history: Vec<f32>,
+    write: usize,
+    /// Smoothed magnitudes from the previous analysis
+    spectrum: Vec<f64>,
+    /// Samples received so far
+    frames: u64,
+    /// dB spectrum of the last analysis and the sample count it was taken at;
+    /// reading twice within a quantum must not smooth twice
+    analysed: Option<(u64, Vec<f64>)>,
+}
+
+impl AnalyserNode {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            fft_size: 2048,
+            min_decibels: -100.0,
+            max_decibels: -30.0,
+            smoothing_time_constant: 0.8,
+            history: vec![0.0; MAX_FFT_SIZE],
+            write: 0,
+            spectrum: Vec::new(),
+            frames: 0,
+            analysed: None,
+        }
+    }
+
+    /// Set the FFT size; must be a power of two between 32 and 32768
+    pub fn set_fft_size(&mut self, size: usize) -> Result<(), &'static str> {
+        if !size.is_power_of_two() || !(32..=MAX_FFT_SIZE).contains(&size) {
+            return Err("IndexSizeError: fftSize must be a power of two in [32, 32768]");
+        }
+        self.fft_size = size;
+        self.spectrum.clear();
+        self.analysed = None;
+        Ok(())
+    }
+
+    /// Set the lower bound of the byte spectrum; must stay below the upper
+    pub fn set_min_decibels(&mut self, min: f64) -> Result<(), &'static str> {
+        if min.is_nan() || min >= self.max_decibels {
+            return Err("IndexSizeError: minDecibels must be less than maxDecibels");
+        }
+        self.min_decibels = min;
+        Ok(())
+    }
+
+    /// Set the upper bound of the byte spectrum; must stay above the lower
+    pub fn set_max_decibels(&mut self, max: f64) -> Result<(), &'static str> {
+        if max.is_nan() || max <= self.min_decibels {
+            return Err("IndexSizeError: maxDecibels must be greater than minDecibels");
+        }
+        self.max_decibels = max;
+        Ok(())
+    }
+
+    pub fn set_smoothing_time_constant(&mut self, value: f64) -> Result<(), &'static str> {
+        if !(0.0..=1.0).contains(&value) {
+            return Err("IndexSizeError: smoothingTimeConstant must be in [0, 1]");
+        }
+        self.smoothing_time_constant = value;
+        Ok(())
+    }
+
+    pub fn frequency_bin_count(&self) -> usize { self.fft_size / 2 }
+
+    /// FFT size actually used (a power of two in range)
+    fn window_size(&self) -> usize {
+        self.fft_size.clamp(32, MAX_FFT_SIZE).next_power_of_two().min(MAX_FFT_SIZE)
+    }
+
+    /// The latest `window_size` input samples, oldest first
+    fn window(&self) -> Vec<f32> {
+        let n = self.window_size();
+        let start = (self.write + MAX_FFT_SIZE - n) % MAX_FFT_SIZE;
+        (0..n).map(|i| self.history[(start + i) % MAX_FFT_SIZE]).collect()
+    }
+
+    /// Compute the smoothed magnitude spectrum in dB
+    fn analyse(&mut self) -> Vec<f64> {
+        let n = self.window_size();
+        if let Some((frames, spectrum)) = &self.analysed {
+            if *frames == self.frames && spectrum.len() == n / 2 {
+                return spectrum.clone();
+            }
+        }
+        let mut re: Vec<f64> = self.window().iter().enumerate().map(|(i, &s)| {
+            // Blackman window, alpha = 0.16
+            let x = 2.0 * PI * i as f64 / n as f64;
+            s as f64 * (0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos())
+        }).collect();
+        let mut im = vec![0.0; n];
+        fft(&mut re, &mut im);
+
+        if self.spectrum.len() != n / 2 {
+            self.spectrum = vec![0.0; n / 2];
+        }
+        let tau = self.smoothing_time_constant.clamp(0.0, 1.0);
+        for (k, smoothed) in self.spectrum.iter_mut().enumerate() {
+            let magnitude = (re[k] * re[k] + im[k] * im[k]).sqrt() / n as f64;
+            *smoothed = tau * *smoothed + (1.0 - tau) * magnitude;
+            if !smoothed.is_finite() {
+                *smoothed = 0.0;
+            }
+        }
+        let db: Vec<f64> = self.spectrum.iter().map(|&m| 20.0 * m.log10()).collect();
+        self.analysed = Some((self.frames, db.clone()));
+        db
+    }
+
+    /// Spectrum in dB; bins with no energy are `-inf`
+    pub fn get_float_frequency_data(&mut self, array: &mut [f32]) {
+        for (dst, db) in array.iter_mut().zip(self.analyse()) {
+            *dst = db as f32;
+        }
+    }
+
+    /// Spectrum scaled from [min_decibels, max_decibels] to [0, 255]
+    pub fn get_byte_frequency_data(&mut self, array: &mut [u8]) {
+        let range = (self.max_decibels - self.min_decibels).max(f64::EPSILON);
+        let min = self.min_decibels;
+        for (dst, db) in array.iter_mut().zip(self.analyse()) {
+            *dst = (255.0 / range * (db - min)).clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    pub fn get_byte_time_domain_data(&self, array: &mut [u8]) {
+        for (dst, s) in array.iter_mut().zip(self.window()) {
+            *dst = (128.0 * (1.0 + s)).clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    pub fn get_float_time_domain_data(&self, array: &mut [f32]) {
+        for (dst, s) in array.iter_mut().zip(self.window()) {
+            *dst = s;
+        }
+    }
+}
+
+impl AudioProcessor for AnalyserNode {
+    fn id(&self) -> u32 { self.id }
+
+    fn process(&mut self, input: &AudioBlock, output: &mut AudioBlock, _quantum: &RenderQuantum) {
+        output.channels.clone_from(&input.channels);
+        for s in input.mono() {
+            self.history[self.write] = s;
+            self.write = (self.write + 1) % MAX_FFT_SIZE;
+        }
+        self.frames += input.frames() as u64;
+    }
+}
+
+/// In-place iterative radix-2 FFT; the length must be a power of two
+pub(super) fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fft_size() {
+        let mut analyser = AnalyserNode::new(1);
+        assert_eq!(analyser.frequency_bin_count(), 1024);
+        assert!(analyser.set_fft_size(1000).is_err());
+        analyser.set_fft_size(256).unwrap();
+        assert_eq!(analyser.frequency_bin_count(), 128);
+        assert!(analyser.set_min_decibels(-20.0).is_err());
+        analyser.set_max_decibels(-10.0).unwrap();
+        assert!(analyser.set_smoothing_time_constant(1.5).is_err());
+    }
+
+    #[test]
+    fn test_spectrum_peak() {
+        let mut analyser = AnalyserNode::new(1);
+        analyser.set_fft_size(1024).unwrap();
+        analyser.smoothing_time_constant = 0.0;
+
+        // Bin 32 of a 1024-point FFT at 48 kHz is 1500 Hz
+        let frames = 1024;
+        let input = AudioBlock {
+            channels: vec![(0..frames).map(|i| (2.0 * PI * 1500.0 * i as f64 / 48000.0).sin() as f32).collect()],
+        };
+        let quantum = RenderQuantum { sample_rate: 48000.0, current_time: 0.0, frames };
+        let mut output = AudioBlock::silent(1, frames);
+        analyser.process(&input, &mut output, &quantum);
+        assert_eq!(output, input);
+
+        let mut spectrum = vec![0.0f32; 512];
+        analyser.get_float_frequency_data(&mut spectrum);
+        let peak = spectrum.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).map(|(i, _)| i);
+        assert_eq!(peak, Some(32));
+
+        // A second read in the same quantum is not smoothed again
+        analyser.smoothing_time_constant = 0.5;
+        let mut again = vec![0.0f32; 512];
+        analyser.get_float_frequency_data(&mut again);
+        assert_eq!(again, spectrum);
+
+        let mut time = vec![0u8; 1024];
+        analyser.get_byte_time_domain_data(&mut time);
+        assert_eq!(time[0], 128);
+    }
+}
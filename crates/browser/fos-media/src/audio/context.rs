@@ -1,6 +1,15 @@
 //! Web Audio API
 //!
-//! AudioContext and audio nodes.
+//! AudioContext and audio nodes. Nodes are configured as plain values, then
+//! moved into the context with [`AudioContext::add_node`] and connected by id.
+
+use super::analyser::AnalyserNode;
+use super::convolver::ConvolverNode;
+use super::filter::{BiquadFilterNode, DynamicsCompressorNode};
+use super::graph::{AudioBlock, AudioGraph, AudioProcessor, RenderQuantum};
+use super::param::AudioParam;
+use super::spatial::{PannerNode, StereoPannerNode};
+use std::f64::consts::PI;
 
 /// Audio context state
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -18,8 +27,10 @@ pub struct AudioContext {
     pub sample_rate: f32,
     pub current_time: f64,
     pub destination: AudioDestinationNode,
-    nodes: Vec<AudioNode>,
+    graph: AudioGraph,
     next_id: u32,
+    /// Last rendered quantum had sound
+    audible: bool,
 }
 
 /// Audio destination node
@@ -49,8 +60,9 @@ impl AudioContext {
             sample_rate: options.sample_rate.unwrap_or(44100.0),
             current_time: 0.0,
             destination: AudioDestinationNode::default(),
-            nodes: Vec::new(),
+            graph: AudioGraph::new(),
             next_id: 1,
+            audible: false,
         }
     }
     
@@ -114,10 +126,83 @@ impl AudioContext {
         BiquadFilterNode::new(id)
     }
     
-    /// Decode audio data
-    pub fn decode_audio_data(&self, _data: &[u8]) -> Result<AudioBuffer, &'static str> {
-        // Would decode audio
-        Ok(AudioBuffer::new(2, 44100, 44100.0))
+    /// Create dynamics compressor
+    pub fn create_dynamics_compressor(&mut self) -> DynamicsCompressorNode {
+        let id = self.next_id;
+        self.next_id += 1;
+        DynamicsCompressorNode::new(id)
+    }
+    
+    /// Create convolver
+    pub fn create_convolver(&mut self) -> ConvolverNode {
+        let id = self.next_id;
+        self.next_id += 1;
+        ConvolverNode::new(id)
+    }
+    
+    /// Create stereo panner
+    pub fn create_stereo_panner(&mut self) -> StereoPannerNode {
+        let id = self.next_id;
+        self.next_id += 1;
+        StereoPannerNode::new(id)
+    }
+    
+    /// Create 3D panner
+    pub fn create_panner(&mut self) -> PannerNode {
+        let id = self.next_id;
+        self.next_id += 1;
+        PannerNode::new(id)
+    }
+    
+    /// Create an empty buffer
+    pub fn create_buffer(&self, channels: u32, length: u32, sample_rate: f32) -> AudioBuffer {
+        AudioBuffer::new(channels, length, sample_rate)
+    }
+    
+    /// Move a configured node into the graph, returning its id
+    pub fn add_node<N: AudioProcessor>(&mut self, node: N) -> u32 {
+        self.graph.add(Box::new(node))
+    }
+    
+    /// Connect a node's output to another node or `DESTINATION`
+    pub fn connect(&mut self, from: u32, to: u32) -> Result<(), &'static str> {
+        self.graph.connect(from, to)
+    }
+    
+    /// Disconnect a node from `to`, or from everything
+    pub fn disconnect(&mut self, from: u32, to: Option<u32>) {
+        self.graph.disconnect(from, to);
+    }
+    
+    /// Take a node out of the graph
+    pub fn remove_node(&mut self, id: u32) {
+        self.graph.remove(id);
+    }
+    
+    /// Node in the graph
+    pub fn node<N: AudioProcessor>(&self, id: u32) -> Option<&N> {
+        self.graph.get(id)
+    }
+    
+    /// Mutable node in the graph (start/stop, parameter changes)
+    pub fn node_mut<N: AudioProcessor>(&mut self, id: u32) -> Option<&mut N> {
+        self.graph.get_mut(id)
+    }
+    
+    /// Render `frames` frames (at most one quantum) at the destination and
+    /// advance the clock
+    pub(crate) fn render_block(&mut self, channels: usize, frames: usize) -> AudioBlock {
+        let quantum = RenderQuantum { sample_rate: self.sample_rate, current_time: self.current_time, frames };
+        let block = self.graph.render(channels, &quantum);
+        self.current_time += frames as f64 / self.sample_rate as f64;
+        self.audible = !block.is_silent();
+        block
+    }
+    
+    /// `decodeAudioData()`: decode a complete FLAC, Ogg, MP4 or WebM file
+    /// into a buffer at the context's sample rate
+    pub fn decode_audio_data(&self, data: &[u8]) -> Result<AudioBuffer, &'static str> {
+        super::decode::decode_audio_file(data, self.sample_rate)
     }
 }
 
@@ -125,6 +210,30 @@ impl Default for AudioContext {
     fn default() -> Self { Self::new() }
 }
 
+/// The context is pulled by the output render pump one quantum at a time;
+/// its clock only advances while running.
+impl crate::output::AudioRenderSource for AudioContext {
+    fn render(&mut self, output: &mut [f32], channels: u32, _sample_rate: u32) {
+        if self.state != AudioContextState::Running {
+            self.audible = false;
+            return;
+        }
+        let channels = channels.max(1) as usize;
+        for chunk in output.chunks_mut(crate::output::RENDER_QUANTUM_FRAMES * channels) {
+            let block = self.render_block(channels, chunk.len() / channels);
+            for (frame, samples) in chunk.chunks_exact_mut(channels).enumerate() {
+                for (sample, channel) in samples.iter_mut().zip(&block.channels) {
+                    *sample += channel[frame];
+                }
+            }
+        }
+    }
+    
+    fn is_audible(&self) -> bool {
+        self.audible
+    }
+}
+
 /// Audio context options
 #[derive(Debug, Clone, Default)]
 pub struct AudioContextOptions {
@@ -172,7 +281,10 @@ pub struct OscillatorNode {
     pub oscillator_type: OscillatorType,
     pub frequency: AudioParam,
     pub detune: AudioParam,
-    started: bool,
+    start_time: Option<f64>,
+    stop_time: Option<f64>,
+    /// Phase in cycles, [0, 1)
+    phase: f64,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -192,12 +304,46 @@ impl OscillatorNode {
             oscillator_type: OscillatorType::Sine,
             frequency: AudioParam::new(440.0),
             detune: AudioParam::new(0.0),
-            started: false,
+            start_time: None,
+            stop_time: None,
+            phase: 0.0,
         }
     }
     
-    pub fn start(&mut self, _when: f64) { self.started = true; }
-    pub fn stop(&mut self, _when: f64) { self.started = false; }
+    pub fn start(&mut self, when: f64) { self.start_time = Some(when.max(0.0)); }
+    pub fn stop(&mut self, when: f64) { self.stop_time = Some(when.max(0.0)); }
+    
+    /// Sample of one cycle at `phase` (in cycles)
+    fn wave(&self, phase: f64) -> f64 {
+        match self.oscillator_type {
+            // Custom waves need a PeriodicWave; they play as sine
+            OscillatorType::Sine | OscillatorType::Custom => (2.0 * PI * phase).sin(),
+            OscillatorType::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            OscillatorType::Sawtooth => 2.0 * phase - 1.0,
+            OscillatorType::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        }
+    }
+}
+
+impl AudioProcessor for OscillatorNode {
+    fn id(&self) -> u32 { self.id }
+    
+    fn output_channels(&self, _input_channels: usize) -> usize { 1 }
+    
+    fn process(&mut self, _input: &AudioBlock, output: &mut AudioBlock, quantum: &RenderQuantum) {
+        let frequency = self.frequency.a_rate(quantum);
+        let detune = self.detune.a_rate(quantum);
+        let Some(start) = self.start_time else { return };
+        for (i, sample) in output.channels[0].iter_mut().enumerate() {
+            let t = quantum.frame_time(i);
+            if t < start || self.stop_time.is_some_and(|stop| t >= stop) {
+                continue;
+            }
+            *sample = self.wave(self.phase) as f32;
+            let step = frequency[i] * 2f64.powf(detune[i] / 1200.0) / quantum.sample_rate as f64;
+            self.phase = (self.phase + step).rem_euclid(1.0);
+        }
+    }
 }
 
 /// Gain node
@@ -213,32 +359,17 @@ impl GainNode {
     }
 }
 
-/// Analyser node
-#[derive(Debug)]
-pub struct AnalyserNode {
-    pub id: u32,
-    pub fft_size: usize,
-    pub min_decibels: f64,
-    pub max_decibels: f64,
-    pub smoothing_time_constant: f64,
-}
-
-impl AnalyserNode {
-    pub fn new(id: u32) -> Self {
-        Self {
-            id,
-            fft_size: 2048,
-            min_decibels: -100.0,
-            max_decibels: -30.0,
-            smoothing_time_constant: 0.8,
+impl AudioProcessor for GainNode {
+    fn id(&self) -> u32 { self.id }
+    
+    fn process(&mut self, input: &AudioBlock, output: &mut AudioBlock, quantum: &RenderQuantum) {
+        let gain = self.gain.a_rate(quantum);
+        for (dst, src) in output.channels.iter_mut().zip(&input.channels) {
+            for ((d, s), g) in dst.iter_mut().zip(src).zip(&gain) {
+                *d = s * *g as f32;
+            }
         }
     }
-    
-    pub fn frequency_bin_count(&self) -> usize { self.fft_size / 2 }
-    pub fn get_byte_frequency_data(&self, _array: &mut [u8]) {}
-    pub fn get_float_frequency_data(&self, _array: &mut [f32]) {}
-    pub fn get_byte_time_domain_data(&self, _array: &mut [u8]) {}
-    pub fn get_float_time_domain_data(&self, _array: &mut [f32]) {}
 }
 
 /// Audio buffer source node
@@ -250,6 +381,13 @@ pub struct AudioBufferSourceNode {
     pub loop_: bool,
     pub loop_start: f64,
     pub loop_end: f64,
+    start_time: Option<f64>,
+    stop_time: Option<f64>,
+    /// Read position in buffer frames
+    position: f64,
+    /// Remaining output frames when a duration was given to `start`
+    remaining: Option<f64>,
+    ended: bool,
 }
 
 impl AudioBufferSourceNode {
@@ -261,11 +399,88 @@ impl AudioBufferSourceNode {
             loop_: false,
             loop_start: 0.0,
             loop_end: 0.0,
+            start_time: None,
+            stop_time: None,
+            position: 0.0,
+            remaining: None,
+            ended: false,
         }
     }
     
-    pub fn start(&mut self, _when: f64, _offset: f64, _duration: Option<f64>) {}
-    pub fn stop(&mut self, _when: f64) {}
+    /// Start playback at context time `when`, `offset` seconds into the buffer,
+    /// for at most `duration` seconds of buffer time
+    pub fn start(&mut self, when: f64, offset: f64, duration: Option<f64>) {
+        let buffer_rate = self.buffer.as_ref().map(|b| b.sample_rate as f64).unwrap_or(0.0);
+        self.start_time = Some(when.max(0.0));
+        self.position = offset.max(0.0) * buffer_rate;
+        self.remaining = duration.map(|d| d.max(0.0) * buffer_rate);
+        self.ended = false;
+    }
+    
+    pub fn stop(&mut self, when: f64) { self.stop_time = Some(when.max(0.0)); }
+    
+    /// Playback reached the end of the buffer or its stop time
+    pub fn has_ended(&self) -> bool { self.ended }
+    
+    /// Loop bounds in buffer frames
+    fn loop_frames(&self, buffer: &AudioBuffer) -> (f64, f64) {
+        let rate = buffer.sample_rate as f64;
+        let length = buffer.length as f64;
+        let end = if self.loop_end > 0.0 { (self.loop_end * rate).min(length) } else { length };
+        let start = (self.loop_start * rate).clamp(0.0, end);
+        if start < end { (start, end) } else { (0.0, length) }
+    }
+}
+
+impl AudioProcessor for AudioBufferSourceNode {
+    fn id(&self) -> u32 { self.id }
+    
+    fn output_channels(&self, _input_channels: usize) -> usize {
+        self.buffer.as_ref().map(|b| b.number_of_channels as usize).unwrap_or(1)
+    }
+    
+    fn process(&mut self, _input: &AudioBlock, output: &mut AudioBlock, quantum: &RenderQuantum) {
+        let playback_rate = self.playback_rate.k_rate(quantum);
+        let (Some(start), Some(buffer)) = (self.start_time, self.buffer.as_ref()) else { return };
+        // Linear-interpolating resampler: buffer frames advanced per output frame
+        let step = playback_rate.max(0.0) * buffer.sample_rate as f64 / quantum.sample_rate as f64;
+        let (loop_start, loop_end) = self.loop_frames(buffer);
+        let length = buffer.length as f64;
+        
+        for i in 0..quantum.frames {
+            let t = quantum.frame_time(i);
+            if self.ended || t < start {
+                continue;
+            }
+            if self.stop_time.is_some_and(|stop| t >= stop)
+                || self.remaining.is_some_and(|r| r <= 0.0)
+                || (!self.loop_ && self.position >= length)
+            {
+                self.ended = true;
+                continue;
+            }
+            
+            let index = self.position.floor();
+            let frac = (self.position - index) as f32;
+            let mut next = index + 1.0;
+            if self.loop_ && next >= loop_end {
+                next = loop_start;
+            }
+            for (channel, data) in output.channels.iter_mut().zip(&buffer.data) {
+                let a = data.get(index as usize).copied().unwrap_or(0.0);
+                let b = data.get(next as usize).copied().unwrap_or(0.0);
+                channel[i] = a + (b - a) * frac;
+            }
+            
+            self.position += step;
+            if let Some(remaining) = self.remaining.as_mut() {
+                *remaining -= step;
+            }
+            if self.loop_ && self.position >= loop_end {
+                self.position = loop_start + (self.position - loop_end) % (loop_end - loop_start).max(1.0);
+            }
+        }
+    }
 }
 
 /// Audio buffer
@@ -287,6 +502,13 @@ impl AudioBuffer {
         }
     }
     
+    /// Buffer holding the given channel data; channels are cut to the shortest
+    pub fn from_channels(data: Vec<Vec<f32>>, sample_rate: f32) -> Self {
+        let length = data.iter().map(|c| c.len()).min().unwrap_or(0);
+        let data: Vec<Vec<f32>> = data.into_iter().map(|mut c| { c.truncate(length); c }).collect();
+        Self { number_of_channels: data.len() as u32, length: length as u32, sample_rate, data }
+    }
+    
     pub fn duration(&self) -> f64 { self.length as f64 / self.sample_rate as f64 }
     pub fn get_channel_data(&self, channel: u32) -> Option<&[f32]> {
         self.data.get(channel as usize).map(|v| v.as_slice())
@@ -304,78 +526,45 @@ impl AudioBuffer {
 pub struct DelayNode {
     pub id: u32,
     pub delay_time: AudioParam,
+    pub max_delay_time: f64,
+    /// Per-channel ring buffers of past input
+    history: Vec<Vec<f32>>,
+    write: usize,
 }
 
 impl DelayNode {
     pub fn new(id: u32, max_delay: f64) -> Self {
-        Self { id, delay_time: AudioParam::new(max_delay.min(1.0)) }
-    }
-}
-
-/// Biquad filter node
-#[derive(Debug)]
-pub struct BiquadFilterNode {
-    pub id: u32,
-    pub filter_type: BiquadFilterType,
-    pub frequency: AudioParam,
-    pub q: AudioParam,
-    pub gain: AudioParam,
-}
-
-#[derive(Debug, Clone, Copy, Default)]
-pub enum BiquadFilterType {
-    #[default]
-    Lowpass,
-    Highpass,
-    Bandpass,
-    Lowshelf,
-    Highshelf,
-    Peaking,
-    Notch,
-    Allpass,
-}
-
-impl BiquadFilterNode {
-    pub fn new(id: u32) -> Self {
         Self {
             id,
-            filter_type: BiquadFilterType::Lowpass,
-            frequency: AudioParam::new(350.0),
-            q: AudioParam::new(1.0),
-            gain: AudioParam::new(0.0),
+            delay_time: AudioParam::with_range(0.0, 0.0, max_delay),
+            max_delay_time: max_delay,
+            history: Vec::new(),
+            write: 0,
         }
     }
 }
 
-/// Audio parameter
-#[derive(Debug, Clone)]
-pub struct AudioParam {
-    pub value: f64,
-    pub default_value: f64,
-    pub min_value: f64,
-    pub max_value: f64,
-}
-
-impl AudioParam {
-    pub fn new(default: f64) -> Self {
-        Self {
-            value: default,
-            default_value: default,
-            min_value: f64::MIN,
-            max_value: f64::MAX,
-        }
-    }
-    
-    pub fn set_value_at_time(&mut self, value: f64, _start_time: f64) {
-        self.value = value;
-    }
+impl AudioProcessor for DelayNode {
+    fn id(&self) -> u32 { self.id }
     
-    pub fn linear_ramp_to_value_at_time(&mut self, value: f64, _end_time: f64) {
-        self.value = value;
-    }
-    
-    pub fn exponential_ramp_to_value_at_time(&mut self, value: f64, _end_time: f64) {
-        self.value = value;
+    fn process(&mut self, input: &AudioBlock, output: &mut AudioBlock, quantum: &RenderQuantum) {
+        let rate = quantum.sample_rate as f64;
+        let size = (self.max_delay_time * rate).ceil() as usize + quantum.frames + 1;
+        if self.history.len() != input.channel_count() || self.history.first().is_some_and(|h| h.len() != size) {
+            self.history = vec![vec![0.0; size]; input.channel_count()];
+            self.write = 0;
+        }
+        let delay_time = self.delay_time.a_rate(quantum);
+        for i in 0..quantum.frames {
+            let delay = (delay_time[i].clamp(0.0, self.max_delay_time) * rate) as usize;
+            let write = (self.write + i) % size;
+            let read = (write + size - delay) % size;
+            for ((history, src), dst) in self.history.iter_mut().zip(&input.channels).zip(output.channels.iter_mut()) {
+                history[write] = src[i];
+                dst[i] = history[read];
+            }
+        }
+        self.write = (self.write + quantum.frames) % size;
     }
 }
 
@@ -392,6 +581,20 @@ mod tests {
         assert_eq!(ctx.state, AudioContextState::Running);
     }
     
+    #[test]
+    fn test_render_quantum_advances_time() {
+        use crate::output::{AudioRenderSource, RENDER_QUANTUM_FRAMES};
+        
+        let mut ctx = AudioContext::with_options(AudioContextOptions { sample_rate: Some(48000.0), ..Default::default() });
+        let mut quantum = vec![0.0; RENDER_QUANTUM_FRAMES * 2];
+        ctx.render(&mut quantum, 2, 48000);
+        assert_eq!(ctx.current_time, 0.0);
+        
+        ctx.resume().unwrap();
+        ctx.render(&mut quantum, 2, 48000);
+        assert!((ctx.current_time - 128.0 / 48000.0).abs() < 1e-12);
+    }
+    
     #[test]
     fn test_oscillator() {
         let mut ctx = AudioContext::new();
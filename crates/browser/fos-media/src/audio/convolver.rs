@@ -0,0 +1,287 @@
+//! Convolver Node
+//!
+//! Linear convolution with an impulse response (reverb, cabinet simulation).
+//! The first partition of the impulse is applied directly so there is no
+//! added latency; the rest uses uniformly partitioned FFT convolution, whose
+//! results are only needed one partition later.
+
+use super::analyser::fft;
+use super::context::AudioBuffer;
+use super::graph::{AudioBlock, AudioProcessor, RenderQuantum};
+use std::collections::VecDeque;
+
+/// Spec constants for impulse normalization
+const GAIN_CALIBRATION: f64 = 0.00125;
+const GAIN_CALIBRATION_SAMPLE_RATE: f64 = 44100.0;
+const MIN_POWER: f64 = 0.000125;
+
+/// Impulse partition length in frames
+const PARTITION: usize = 128;
+
+/// Convolver node
+#[derive(Debug)]
+pub struct ConvolverNode {
+    pub id: u32,
+    /// Scale the impulse response to an equal-power level when it is set
+    pub normalize: bool,
+    buffer: Option<AudioBuffer>,
+    /// Impulse channels after normalization
+    kernel: Vec<Vec<f32>>,
+    /// (input channel, output channel, convolution) for the current layout
+    paths: Vec<(usize, usize, Convolution)>,
+    /// Input channel count the paths were built for
+    layout: usize,
+}
+
+impl ConvolverNode {
+    pub fn new(id: u32) -> Self {
+        Self { id, normalize: true, buffer: None, kernel: Vec::new(), paths: Vec::new(), layout: 0 }
+    }
+
+    pub fn buffer(&self) -> Option<&AudioBuffer> { self.buffer.as_ref() }
+
+    /// Set the impulse response: 1 channel, 2 (one per output) or 4 for
+    /// true stereo (L->L, R->L, L->R, R->R)
+    pub fn set_buffer(&mut self, buffer: Option<AudioBuffer>) -> Result<(), &'static str> {
+        if let Some(b) = &buffer {
+            if !matches!(b.number_of_channels, 1 | 2 | 4) {
+                return Err("NotSupportedError: impulse response must have 1, 2 or 4 channels");
+            }
+        }
+        self.kernel = buffer.as_ref().map(|b| {
+            let scale = if self.normalize { normalization_scale(b) } else { 1.0 };
+            (0..b.number_of_channels)
+                .filter_map(|c| b.get_channel_data(c))
+                .map(|data| data.iter().map(|&s| s * scale).collect())
+                .collect()
+        }).unwrap_or_default();
+        self.paths.clear();
+        self.buffer = buffer;
+        Ok(())
+    }
+
+    /// Build the convolution paths for a mono or stereo input
+    fn route(&mut self, inputs: usize) {
+        let (left, right) = (0, inputs.min(2) - 1);
+        let routes: Vec<(usize, usize, usize)> = match self.kernel.len() {
+            1 => (0..inputs.min(2)).map(|c| (c, c, 0)).collect(),
+            2 => vec![(left, 0, 0), (right, 1, 1)],
+            _ => vec![(left, 0, 0), (right, 0, 1), (left, 1, 2), (right, 1, 3)],
+        };
+        self.paths = routes.into_iter()
+            .map(|(from, to, k)| (from, to, Convolution::new(&self.kernel[k])))
+            .collect();
+        self.layout = inputs;
+    }
+}
+
+/// One input channel convolved with one impulse channel
+#[derive(Debug)]
+struct Convolution {
+    /// First partition of the impulse, applied sample by sample
+    head: Vec<f32>,
+    /// Spectra of the remaining partitions (bins 0..=PARTITION of a
+    /// 2 * PARTITION point FFT)
+    tail: Vec<(Vec<f64>, Vec<f64>)>,
+    /// Ring of the latest PARTITION input samples, indexed by `position`
+    recent: Vec<f32>,
+    /// Previous and current input block, for overlap-save
+    window: Vec<f64>,
+    /// Spectra of completed input blocks, newest first
+    spectra: VecDeque<(Vec<f64>, Vec<f64>)>,
+    /// Tail contribution to the block being output
+    pending: Vec<f32>,
+    /// Frame within the current block
+    position: usize,
+}
+
+impl Convolution {
+    fn new(kernel: &[f32]) -> Self {
+        let split = kernel.len().min(PARTITION);
+        let tail = kernel[split..].chunks(PARTITION).map(|chunk| {
+            let mut re = vec![0.0; 2 * PARTITION];
+            for (dst, &src) in re.iter_mut().zip(chunk) {
+                *dst = src as f64;
+            }
+            spectrum(re)
+        }).collect();
+        Self {
+            head: kernel[..split].to_vec(),
+            tail,
+            recent: vec![0.0; PARTITION],
+            window: vec![0.0; 2 * PARTITION],
+            spectra: VecDeque::new(),
+            pending: vec![0.0; PARTITION],
+            position: 0,
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let p = self.position;
+        self.recent[p] = x;
+        self.window[PARTITION + p] = x as f64;
+        let mut y = self.pending[p];
+        for (k, &h) in self.head.iter().enumerate() {
+            y += h * self.recent[(p + PARTITION - k) % PARTITION];
+        }
+        self.position += 1;
+        if self.position == PARTITION {
+            self.position = 0;
+            self.finish_block();
+        }
+        y
+    }
+
+    /// Input block complete: compute the tail part of the next block, where
+    /// partition `p` meets the input block `p` blocks back
+    fn finish_block(&mut self) {
+        if self.tail.is_empty() {
+            return;
+        }
+        self.spectra.push_front(spectrum(self.window.clone()));
+        self.spectra.truncate(self.tail.len());
+        self.window.copy_within(PARTITION.., 0);
+
+        let n = 2 * PARTITION;
+        let (mut re, mut im) = (vec![0.0; n], vec![0.0; n]);
+        for ((xr, xi), (hr, hi)) in self.spectra.iter().zip(&self.tail) {
+            for k in 0..=PARTITION {
+                re[k] += xr[k] * hr[k] - xi[k] * hi[k];
+                im[k] += xr[k] * hi[k] + xi[k] * hr[k];
+            }
+        }
+        // Real signal: the upper bins mirror the lower ones. Inverse FFT by
+        // conjugating around a forward one.
+        for k in PARTITION + 1..n {
+            re[k] = re[n - k];
+            im[k] = -im[n - k];
+        }
+        for v in im.iter_mut() {
+            *v = -*v;
+        }
+        fft(&mut re, &mut im);
+        for (dst, &v) in self.pending.iter_mut().zip(&re[PARTITION..]) {
+            *dst = (v / n as f64) as f32;
+        }
+    }
+}
+
+/// Bins 0..=PARTITION of the FFT of a real 2 * PARTITION point signal
+fn spectrum(mut re: Vec<f64>) -> (Vec<f64>, Vec<f64>) {
+    let mut im = vec![0.0; re.len()];
+    fft(&mut re, &mut im);
+    re.truncate(PARTITION + 1);
+    im.truncate(PARTITION + 1);
+    (re, im)
+}
+
+/// Equal-power scale for an impulse response
+fn normalization_scale(buffer: &AudioBuffer) -> f32 {
+    let mut sum = 0.0f64;
+    for c in 0..buffer.number_of_channels {
+        sum += buffer.get_channel_data(c).unwrap_or(&[]).iter().map(|&s| (s as f64).powi(2)).sum::<f64>();
+    }
+    let samples = (buffer.number_of_channels as f64 * buffer.length as f64).max(1.0);
+    let mut power = (sum / samples).sqrt();
+    if !power.is_finite() || power < MIN_POWER {
+        power = MIN_POWER;
+    }
+    let scale = GAIN_CALIBRATION / power * GAIN_CALIBRATION_SAMPLE_RATE / buffer.sample_rate.max(1.0) as f64;
+    scale as f32
+}
+
+impl AudioProcessor for ConvolverNode {
+    fn id(&self) -> u32 { self.id }
+
+    fn output_channels(&self, input_channels: usize) -> usize {
+        // Stereo and true-stereo impulses always yield stereo; a mono one
+        // follows the input
+        if self.kernel.len() >= 2 { 2 } else { input_channels.clamp(1, 2) }
+    }
+
+    fn process(&mut self, input: &AudioBlock, output: &mut AudioBlock, quantum: &RenderQuantum) {
+        if self.kernel.is_empty() {
+            return;
+        }
+        let inputs = input.channel_count().clamp(1, 2);
+        if self.paths.is_empty() || self.layout != inputs {
+            self.route(inputs);
+        }
+        for (from, to, convolution) in self.paths.iter_mut() {
+            let (source, out) = (&input.channels[*from], &mut output.channels[*to]);
+            for (y, &x) in out.iter_mut().zip(source).take(quantum.frames) {
+                *y += convolution.process(x);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_echo_impulse() {
+        let mut convolver = ConvolverNode::new(1);
+        convolver.normalize = false;
+        let mut impulse = vec![0.0; 200];
+        impulse[0] = 1.0;
+        impulse[150] = 0.5;
+        convolver.set_buffer(Some(AudioBuffer::from_channels(vec![impulse], 48000.0))).unwrap();
+
+        let quantum = RenderQuantum { sample_rate: 48000.0, current_time: 0.0, frames: 128 };
+        let mut click = AudioBlock::silent(1, 128);
+        click.channels[0][0] = 1.0;
+        let mut first = AudioBlock::silent(1, 128);
+        convolver.process(&click, &mut first, &quantum);
+        assert_eq!(first.channels[0][0], 1.0);
+
+        // The echo lands in the next quantum, after the input went silent
+        let mut second = AudioBlock::silent(1, 128);
+        convolver.process(&AudioBlock::silent(1, 128), &mut second, &quantum);
+        assert_eq!(second.channels[0][150 - 128], 0.5);
+    }
+
+    #[test]
+    fn test_long_impulse_matches_direct_convolution() {
+        let impulse: Vec<f32> = (0..1000).map(|i| ((i * 37 % 101) as f32 / 50.0 - 1.0) * 0.9f32.powi(i / 100)).collect();
+        let signal: Vec<f32> = (0..2000).map(|i| (i * 53 % 97) as f32 / 48.0 - 1.0).collect();
+        let mut convolver = ConvolverNode::new(1);
+        convolver.normalize = false;
+        convolver.set_buffer(Some(AudioBuffer::from_channels(vec![impulse.clone()], 48000.0))).unwrap();
+
+        // Quanta that do not line up with the partitions
+        let mut output = Vec::new();
+        for chunk in signal.chunks(100) {
+            let quantum = RenderQuantum { sample_rate: 48000.0, current_time: 0.0, frames: chunk.len() };
+            let mut block = AudioBlock::silent(1, chunk.len());
+            convolver.process(&AudioBlock { channels: vec![chunk.to_vec()] }, &mut block, &quantum);
+            output.extend_from_slice(&block.channels[0]);
+        }
+        for n in (0..signal.len()).step_by(7) {
+            let expected: f32 = (0..=n.min(impulse.len() - 1)).map(|k| impulse[k] * signal[n - k]).sum();
+            assert!((output[n] - expected).abs() < 1e-3, "frame {n}: {} != {expected}", output[n]);
+        }
+    }
+
+    #[test]
+    fn test_true_stereo_routing() {
+        let mut convolver = ConvolverNode::new(1);
+        convolver.normalize = false;
+        // L->L, R->L, L->R, R->R as single-tap gains
+        let impulse = vec![vec![1.0], vec![0.5], vec![0.25], vec![2.0]];
+        convolver.set_buffer(Some(AudioBuffer::from_channels(impulse, 48000.0))).unwrap();
+        assert_eq!(convolver.output_channels(1), 2);
+
+        let quantum = RenderQuantum { sample_rate: 48000.0, current_time: 0.0, frames: 1 };
+        let mut output = AudioBlock::silent(2, 1);
+        convolver.process(&AudioBlock { channels: vec![vec![1.0], vec![2.0]] }, &mut output, &quantum);
+        assert_eq!(output.channels, vec![vec![2.0], vec![4.25]]);
+    }
+
+    #[test]
+    fn test_rejects_three_channel_impulse() {
+        let mut convolver = ConvolverNode::new(1);
+        assert!(convolver.set_buffer(Some(AudioBuffer::new(3, 10, 48000.0))).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Audio File Decoding
+//!
+//! `decodeAudioData()`: sniffs the file (native FLAC, Ogg, MP4,
+//! WebM/Matroska), decodes its first audio track in full and resamples it
+//! to the context's sample rate.
+
+use super::context::AudioBuffer;
+use crate::containers::{self, mp4::Mp4Demuxer, webm::WebMDemuxer, ContainerFormat, Demuxer, DemuxerError};
+use crate::decoders::audio::flac::{self, FlacDecoder};
+use crate::decoders::audio::opus::OpusHead;
+use crate::decoders::{AudioDecoderTrait, EncodedPacket};
+use crate::codecs::CodecType;
+use crate::pipeline::renderer::resample_by_rate;
+use crate::pipeline::AudioDecoderBackend;
+use std::time::Duration;
+
+/// Interleaved samples of a whole file
+struct DecodedAudio {
+    data: Vec<f32>,
+    channels: u32,
+    sample_rate: u32,
+}
+
+/// Decode a complete audio file into a buffer at `sample_rate`
+pub fn decode_audio_file(data: &[u8], sample_rate: f32) -> Result<AudioBuffer, &'static str> {
+    let decoded = if data.starts_with(b"fLaC") {
+        decode_flac(data)?
+    } else if data.starts_with(b"OggS") {
+        decode_ogg(data)?
+    } else {
+        decode_container(data)?
+    };
+    if decoded.channels == 0 || decoded.sample_rate == 0 {
+        return Err("No audio data");
+    }
+    let rate = decoded.sample_rate as f64 / sample_rate as f64;
+    let data = if rate == 1.0 { decoded.data } else { resample_by_rate(&decoded.data, decoded.channels, rate) };
+    let channels = decoded.channels as usize;
+    let planar = (0..channels).map(|c| data.iter().skip(c).step_by(channels).copied().collect()).collect();
+    Ok(AudioBuffer::from_channels(planar, sample_rate))
+}
+
+/// Native FLAC stream: the stream header, then frames back to back
+fn decode_flac(data: &[u8]) -> Result<DecodedAudio, &'static str> {
+    let (info, mut pos) = flac::parse_stream_header(data).map_err(|_| "Invalid FLAC stream header")?;
+    let mut decoder = FlacDecoder::new();
+    decoder.configure(&data[..pos]).map_err(|_| "Invalid FLAC stream header")?;
+    let mut out = Vec::new();
+    while pos < data.len() {
+        match decoder.decode_frame(&data[pos..], Duration::ZERO) {
+            Ok((samples, len)) => {
+                out.extend(samples.data);
+                pos += len;
+            }
+            // Trailing tags after the last frame
+            Err(_) if !out.is_empty() => break,
+            Err(_) => return Err("Invalid FLAC frame"),
+        }
+    }
+    if info.total_samples > 0 {
+        out.truncate(info.total_samples as usize * info.channels as usize);
+    }
+    Ok(DecodedAudio { data: out, channels: info.channels as u32, sample_rate: info.sample_rate })
+}
+
+/// Ogg Opus, Vorbis or FLAC: the first logical stream, trimmed to the
+/// granule position of its last page
+fn decode_ogg(data: &[u8]) -> Result<DecodedAudio, &'static str> {
+    let (packets, last_granule) = ogg_packets(data).ok_or("Invalid Ogg stream")?;
+    let first = packets.first().ok_or("Empty Ogg stream")?;
+    let (codec, pre_skip) = if first.starts_with(b"OpusHead") {
+        let head = OpusHead::parse(first).map_err(|_| "Invalid OpusHead")?;
+        (CodecType::Opus, head.pre_skip as i64)
+    } else if first.starts_with(b"\x01vorbis") {
+        (CodecType::Vorbis, 0)
+    } else if first.starts_with(b"\x7FFLAC") {
+        (CodecType::FLAC, 0)
+    } else {
+        return Err("Unsupported Ogg codec");
+    };
+    let mut backend = AudioDecoderBackend::for_codec(codec).ok_or("Unsupported Ogg codec")?;
+    let decoder = backend.decoder();
+    let mut out = Vec::new();
+    // Headers are decoded in band
+    for packet in packets {
+        let packet = EncodedPacket { data: packet, pts: Duration::ZERO, dts: Duration::ZERO, is_key: true };
+        out.extend(decoder.decode(&packet).map_err(|_| "Audio decoding failed")?.data);
+    }
+    let channels = decoder.channels();
+    // The final page ends the stream early to drop encoder padding
+    let total = last_granule - pre_skip;
+    if total >= 0 {
+        out.truncate(total as usize * channels as usize);
+    }
+    Ok(DecodedAudio { data: out, channels, sample_rate: decoder.sample_rate() })
+}
+
+/// MP4 or WebM/Matroska: every packet of the first audio track
+fn decode_container(data: &[u8]) -> Result<DecodedAudio, &'static str> {
+    let mut demuxer: Box<dyn Demuxer> = match containers::detect_format(data).ok_or("Unknown audio format")? {
+        ContainerFormat::Mp4 => Box::new(Mp4Demuxer::new(data.to_vec()).map_err(|_| "Invalid MP4 file")?),
+        ContainerFormat::WebM | ContainerFormat::Mkv => Box::new(WebMDemuxer::new(data.to_vec()).map_err(|_| "Invalid WebM file")?),
+        ContainerFormat::MpegTs | ContainerFormat::FragmentedMp4 => return Err("Unsupported audio container"),
+    };
+    let track = demuxer.audio_track().ok_or("No audio track")?.clone();
+    let mut backend = containers::codec_type(track.codec)
+        .and_then(AudioDecoderBackend::for_codec)
+        .ok_or("Unsupported audio codec")?;
+    let decoder = backend.decoder();
+    if let Some(description) = track.description() {
+        decoder.configure(&description).map_err(|_| "Invalid codec configuration")?;
+    }
+    let mut out = Vec::new();
+    loop {
+        match demuxer.read_packet() {
+            Ok(packet) if packet.track_id == track.track_id => {
+                out.extend(decoder.decode(&EncodedPacket::from(&packet)).map_err(|_| "Audio decoding failed")?.data);
+            }
+            Ok(_) => {}
+            Err(DemuxerError::EndOfStream) => break,
+            Err(_) => return Err("Invalid container"),
+        }
+    }
+    Ok(DecodedAudio { data: out, channels: decoder.channels(), sample_rate: decoder.sample_rate() })
+}
+
+/// Packets of the first logical bitstream, and the granule position of its
+/// last page. Packets may span pages; pages of other streams are skipped.
+fn ogg_packets(mut data: &[u8]) -> Option<(Vec<Vec<u8>>, i64)> {
+    let mut serial = None;
+    let mut packets = Vec::new();
+    let mut partial = Vec::new();
+    let mut granule = -1;
+    while data.len() >= 27 && data.starts_with(b"OggS") {
+        let segments = data[26] as usize;
+        let lacing = data.get(27..27 + segments)?;
+        let body_len: usize = lacing.iter().map(|&l| l as usize).sum();
+        let mut body = data.get(27 + segments..27 + segments + body_len)?;
+        let page_serial = u32::from_le_bytes(data[14..18].try_into().ok()?);
+        if *serial.get_or_insert(page_serial) == page_serial {
+            let page_granule = i64::from_le_bytes(data[6..14].try_into().ok()?);
+            // -1: no packet ends on this page
+            if page_granule != -1 {
+                granule = page_granule;
+            }
+            for &lace in lacing {
+                partial.extend_from_slice(&body[..lace as usize]);
+                body = &body[lace as usize..];
+                if lace < 255 {
+                    packets.push(std::mem::take(&mut partial));
+                }
+            }
+        }
+        data = &data[27 + segments + body_len..];
+    }
+    Some((packets, granule))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codecs::{CodecConfig, CodecRegistry};
+
+    /// Ogg page holding whole packets of less than 255 bytes
+    fn page(granule: i64, packets: &[&[u8]]) -> Vec<u8> {
+        let mut page = b"OggS\0\0".to_vec();
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        page.push(packets.len() as u8);
+        page.extend(packets.iter().map(|p| p.len() as u8));
+        for packet in packets {
+            page.extend_from_slice(packet);
+        }
+        page
+    }
+
+    #[test]
+    fn test_decode_ogg_opus() {
+        let registry = CodecRegistry::new();
+        let mut encoder = registry.create_audio_encoder(CodecConfig::audio(CodecType::Opus, 48000, 2)).unwrap();
+        let packets = encoder.encode(&[0.0; 3 * 960 * 2], 0.0).unwrap();
+        assert_eq!(packets.len(), 3);
+        let head = encoder.codec_private();
+        let mut file = page(0, &[&head]);
+        file.extend(page(0, &[b"OpusTags\0\0\0\0\0\0\0\0"]));
+        // 2000 samples after the pre-skip
+        let audio: Vec<&[u8]> = packets.iter().map(|p| p.data.as_slice()).collect();
+        file.extend(page(312 + 2000, &audio));
+
+        let buffer = decode_audio_file(&file, 48000.0).unwrap();
+        assert_eq!((buffer.number_of_channels, buffer.length), (2, 2000));
+        assert!(buffer.get_channel_data(1).unwrap().iter().all(|&s| s == 0.0));
+        // Resampled to the context rate
+        let buffer = decode_audio_file(&file, 24000.0).unwrap();
+        assert_eq!((buffer.length, buffer.sample_rate), (1000, 24000.0));
+    }
+
+    #[test]
+    fn test_packets_span_pages() {
+        let long = vec![7u8; 300];
+        let mut first = b"OggS\0\x02".to_vec();
+        first.extend_from_slice(&(-1i64).to_le_bytes());
+        first.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        first.extend_from_slice(&[1, 255]);
+        first.extend_from_slice(&long[..255]);
+        let mut second = page(480, &[&long[255..], b"abc"]);
+        second[5] = 1;
+        let (packets, granule) = ogg_packets(&[first, second].concat()).unwrap();
+        assert_eq!(packets, vec![long, b"abc".to_vec()]);
+        assert_eq!(granule, 480);
+        assert!(decode_audio_file(b"not an audio file at all", 48000.0).is_err());
+    }
+}
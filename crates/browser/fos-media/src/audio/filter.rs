@@ -0,0 +1,294 @@
+//! Filters and Dynamics
+//!
+//! BiquadFilterNode (Audio EQ Cookbook coefficients, as specified by Web
+//! Audio) and DynamicsCompressorNode.
+
+use super::param::AudioParam;
+use super::graph::{AudioBlock, AudioProcessor, RenderQuantum};
+use std::f64::consts::PI;
+
+/// Biquad filter node
+#[derive(Debug)]
+pub struct BiquadFilterNode {
+    pub id: u32,
+    pub filter_type: BiquadFilterType,
+    pub frequency: AudioParam,
+    pub detune: AudioParam,
+    pub q: AudioParam,
+    pub gain: AudioParam,
+    /// Per-channel history (x1, x2, y1, y2)
+    state: Vec<[f64; 4]>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BiquadFilterType {
+    #[default]
+    Lowpass,
+    Highpass,
+    Bandpass,
+    Lowshelf,
+    Highshelf,
+    Peaking,
+    Notch,
+    Allpass,
+}
+
+/// Normalized biquad coefficients (b0, b1, b2, a1, a2)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Coefficients([f64; 5]);
+
+impl BiquadFilterNode {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            filter_type: BiquadFilterType::Lowpass,
+            frequency: AudioParam::new(350.0),
+            detune: AudioParam::new(0.0),
+            q: AudioParam::new(1.0),
+            gain: AudioParam::new(0.0),
+            state: Vec::new(),
+        }
+    }
+
+    fn coefficients(&self, sample_rate: f64) -> Coefficients {
+        let nyquist = sample_rate / 2.0;
+        let f0 = (self.frequency.value * 2f64.powf(self.detune.value / 1200.0)).clamp(0.0, nyquist);
+        let w0 = 2.0 * PI * f0 / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let a = 10f64.powf(self.gain.value / 40.0);
+        let q = self.q.value;
+        // Lowpass/highpass take Q in dB; the rest use it linearly
+        let alpha_q_db = sin / (2.0 * 10f64.powf(q / 20.0));
+        let alpha_q = sin / (2.0 * q.max(f64::EPSILON));
+        let alpha_s = sin / 2.0 * 2f64.sqrt();
+        let sqrt_a = a.sqrt();
+
+        let [b0, b1, b2, a0, a1, a2] = match self.filter_type {
+            BiquadFilterType::Lowpass => [
+                (1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0,
+                1.0 + alpha_q_db, -2.0 * cos, 1.0 - alpha_q_db,
+            ],
+            BiquadFilterType::Highpass => [
+                (1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0,
+                1.0 + alpha_q_db, -2.0 * cos, 1.0 - alpha_q_db,
+            ],
+            BiquadFilterType::Bandpass => [
+                alpha_q, 0.0, -alpha_q,
+                1.0 + alpha_q, -2.0 * cos, 1.0 - alpha_q,
+            ],
+            BiquadFilterType::Notch => [
+                1.0, -2.0 * cos, 1.0,
+                1.0 + alpha_q, -2.0 * cos, 1.0 - alpha_q,
+            ],
+            BiquadFilterType::Allpass => [
+                1.0 - alpha_q, -2.0 * cos, 1.0 + alpha_q,
+                1.0 + alpha_q, -2.0 * cos, 1.0 - alpha_q,
+            ],
+            BiquadFilterType::Peaking => [
+                1.0 + alpha_q * a, -2.0 * cos, 1.0 - alpha_q * a,
+                1.0 + alpha_q / a, -2.0 * cos, 1.0 - alpha_q / a,
+            ],
+            BiquadFilterType::Lowshelf => [
+                a * ((a + 1.0) - (a - 1.0) * cos + 2.0 * alpha_s * sqrt_a),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - 2.0 * alpha_s * sqrt_a),
+                (a + 1.0) + (a - 1.0) * cos + 2.0 * alpha_s * sqrt_a,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - 2.0 * alpha_s * sqrt_a,
+            ],
+            BiquadFilterType::Highshelf => [
+                a * ((a + 1.0) + (a - 1.0) * cos + 2.0 * alpha_s * sqrt_a),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - 2.0 * alpha_s * sqrt_a),
+                (a + 1.0) - (a - 1.0) * cos + 2.0 * alpha_s * sqrt_a,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - 2.0 * alpha_s * sqrt_a,
+            ],
+        };
+        Coefficients([b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0])
+    }
+
+    /// Magnitude and phase response at the given frequencies (Hz)
+    pub fn get_frequency_response(&self, frequencies: &[f32], magnitude: &mut [f32], phase: &mut [f32], sample_rate: f32) {
+        let Coefficients([b0, b1, b2, a1, a2]) = self.coefficients(sample_rate as f64);
+        for (i, &f) in frequencies.iter().enumerate() {
+            let w = 2.0 * PI * f as f64 / sample_rate as f64;
+            // H(e^jw) = (b0 + b1 z^-1 + b2 z^-2) / (1 + a1 z^-1 + a2 z^-2)
+            let (s1, c1) = (-w).sin_cos();
+            let (s2, c2) = (-2.0 * w).sin_cos();
+            let num = (b0 + b1 * c1 + b2 * c2, b1 * s1 + b2 * s2);
+            let den = (1.0 + a1 * c1 + a2 * c2, a1 * s1 + a2 * s2);
+            let den_mag = den.0 * den.0 + den.1 * den.1;
+            let re = (num.0 * den.0 + num.1 * den.1) / den_mag;
+            let im = (num.1 * den.0 - num.0 * den.1) / den_mag;
+            if let Some(m) = magnitude.get_mut(i) {
+                *m = (re * re + im * im).sqrt() as f32;
+            }
+            if let Some(p) = phase.get_mut(i) {
+                *p = im.atan2(re) as f32;
+            }
+        }
+    }
+}
+
+impl AudioProcessor for BiquadFilterNode {
+    fn id(&self) -> u32 { self.id }
+
+    fn process(&mut self, input: &AudioBlock, output: &mut AudioBlock, quantum: &RenderQuantum) {
+        // Coefficients are computed once per quantum from k-rate values
+        for param in [&mut self.frequency, &mut self.detune, &mut self.q, &mut self.gain] {
+            param.k_rate(quantum);
+        }
+        let Coefficients([b0, b1, b2, a1, a2]) = self.coefficients(quantum.sample_rate as f64);
+        self.state.resize(input.channel_count(), [0.0; 4]);
+        for ((state, src), dst) in self.state.iter_mut().zip(&input.channels).zip(output.channels.iter_mut()) {
+            let [mut x1, mut x2, mut y1, mut y2] = *state;
+            for (d, &s) in dst.iter_mut().zip(src) {
+                let x = s as f64;
+                let y = b0 * x + b1 * x1 + b2 * x2 - a1 * y1 - a2 * y2;
+                (x2, x1, y2, y1) = (x1, x, y1, y);
+                *d = y as f32;
+            }
+            *state = [x1, x2, y1, y2];
+        }
+    }
+}
+
+/// Dynamics compressor node
+///
+/// Peak-detecting compressor with a soft knee; gain changes follow the
+/// attack and release times. No makeup gain is applied.
+#[derive(Debug)]
+pub struct DynamicsCompressorNode {
+    pub id: u32,
+    /// dB above which compression starts
+    pub threshold: AudioParam,
+    /// dB range of the soft knee around the threshold
+    pub knee: AudioParam,
+    pub ratio: AudioParam,
+    /// Seconds to reduce gain by 10 dB
+    pub attack: AudioParam,
+    /// Seconds to recover gain by 10 dB
+    pub release: AudioParam,
+    /// Current gain reduction in dB (<= 0)
+    pub reduction: f32,
+}
+
+impl DynamicsCompressorNode {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            threshold: AudioParam::with_range(-24.0, -100.0, 0.0),
+            knee: AudioParam::with_range(30.0, 0.0, 40.0),
+            ratio: AudioParam::with_range(12.0, 1.0, 20.0),
+            attack: AudioParam::with_range(0.003, 0.0, 1.0),
+            release: AudioParam::with_range(0.25, 0.0, 1.0),
+            reduction: 0.0,
+        }
+    }
+
+    /// Static curve: gain change in dB for an input level in dB
+    fn gain_db(&self, level_db: f64) -> f64 {
+        let threshold = self.threshold.value;
+        let knee = self.knee.value.max(0.0);
+        let slope = 1.0 / self.ratio.value.max(1.0) - 1.0;
+        let over = level_db - threshold;
+        if 2.0 * over < -knee {
+            0.0
+        } else if knee > 0.0 && 2.0 * over.abs() <= knee {
+            slope * (over + knee / 2.0).powi(2) / (2.0 * knee)
+        } else {
+            slope * over
+        }
+    }
+}
+
+impl AudioProcessor for DynamicsCompressorNode {
+    fn id(&self) -> u32 { self.id }
+
+    fn process(&mut self, input: &AudioBlock, output: &mut AudioBlock, quantum: &RenderQuantum) {
+        for param in [&mut self.threshold, &mut self.knee, &mut self.ratio, &mut self.attack, &mut self.release] {
+            param.k_rate(quantum);
+        }
+        let rate = quantum.sample_rate as f64;
+        let coefficient = |time: f64| (-1.0 / (time.max(1e-4) * rate)).exp();
+        let (attack, release) = (coefficient(self.attack.value), coefficient(self.release.value));
+        let mut reduction = self.reduction as f64;
+
+        for i in 0..quantum.frames {
+            let peak = input.channels.iter().map(|c| c[i].abs()).fold(0.0f32, f32::max) as f64;
+            let level_db = if peak > 0.0 { 20.0 * peak.log10() } else { -1000.0 };
+            let target = self.gain_db(level_db);
+            let k = if target < reduction { attack } else { release };
+            reduction = target + (reduction - target) * k;
+
+            let gain = 10f64.powf(reduction / 20.0) as f32;
+            for (dst, src) in output.channels.iter_mut().zip(&input.channels) {
+                dst[i] = src[i] * gain;
+            }
+        }
+        self.reduction = reduction as f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f64, sample_rate: f64, frames: usize) -> AudioBlock {
+        AudioBlock {
+            channels: vec![(0..frames).map(|i| (2.0 * PI * frequency * i as f64 / sample_rate).sin() as f32).collect()],
+        }
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_lowpass_attenuates_high_frequencies() {
+        let quantum = RenderQuantum { sample_rate: 48000.0, current_time: 0.0, frames: 4800 };
+        let mut filter = BiquadFilterNode::new(1);
+        filter.frequency.value = 500.0;
+
+        let mut low = AudioBlock::silent(1, 4800);
+        filter.process(&sine(100.0, 48000.0, 4800), &mut low, &quantum);
+        let mut filter = BiquadFilterNode::new(1);
+        filter.frequency.value = 500.0;
+        let mut high = AudioBlock::silent(1, 4800);
+        filter.process(&sine(8000.0, 48000.0, 4800), &mut high, &quantum);
+
+        assert!(rms(&low.channels[0]) > 0.6);
+        assert!(rms(&high.channels[0]) < 0.01);
+    }
+
+    #[test]
+    fn test_frequency_response() {
+        let mut filter = BiquadFilterNode::new(1);
+        filter.filter_type = BiquadFilterType::Peaking;
+        filter.frequency.value = 1000.0;
+        filter.gain.value = 6.0;
+        let mut magnitude = [0.0; 2];
+        let mut phase = [0.0; 2];
+        filter.get_frequency_response(&[1000.0, 20.0], &mut magnitude, &mut phase, 48000.0);
+        assert!((magnitude[0] - 10f32.powf(6.0 / 20.0)).abs() < 1e-3);
+        assert!((magnitude[1] - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_compressor_reduces_loud_input() {
+        let quantum = RenderQuantum { sample_rate: 48000.0, current_time: 0.0, frames: 4800 };
+        let mut compressor = DynamicsCompressorNode::new(1);
+        let mut output = AudioBlock::silent(1, 4800);
+        compressor.process(&sine(440.0, 48000.0, 4800), &mut output, &quantum);
+        assert!(compressor.reduction < -10.0);
+        assert!(rms(&output.channels[0][2400..]) < 0.3);
+
+        // Quiet input passes untouched
+        let mut compressor = DynamicsCompressorNode::new(1);
+        let quiet = AudioBlock { channels: vec![vec![0.001; 128]] };
+        let mut output = AudioBlock::silent(1, 128);
+        compressor.process(&quiet, &mut output, &RenderQuantum { frames: 128, ..quantum });
+        assert_eq!(output, quiet);
+    }
+}
@@ -0,0 +1,276 @@
+//! Audio Graph
+//!
+//! Render-quantum processing for connected audio nodes. Nodes are moved into
+//! a context with `AudioContext::add_node` and addressed by id afterwards.
+//! Each quantum the graph is evaluated in dependency order, starting from
+//! the nodes feeding the destination.
+
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+
+/// Node id of the context's destination
+pub const DESTINATION: u32 = 0;
+
+/// One render quantum of planar audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioBlock {
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl AudioBlock {
+    /// Silent block
+    pub fn silent(channels: usize, frames: usize) -> Self {
+        Self { channels: vec![vec![0.0; frames]; channels.max(1)] }
+    }
+
+    pub fn channel_count(&self) -> usize { self.channels.len() }
+
+    pub fn frames(&self) -> usize { self.channels.first().map(|c| c.len()).unwrap_or(0) }
+
+    pub fn is_silent(&self) -> bool {
+        self.channels.iter().all(|c| c.iter().all(|&s| s == 0.0))
+    }
+
+    /// Add `other` into this block, up/down-mixing between mono and stereo
+    /// with the "speakers" rules; other layouts mix channel by channel
+    pub fn mix_from(&mut self, other: &AudioBlock) {
+        match (other.channel_count(), self.channel_count()) {
+            (1, n) => {
+                for channel in self.channels.iter_mut().take(n.min(2)) {
+                    add_into(channel, &other.channels[0], 1.0);
+                }
+            }
+            (2, 1) => {
+                add_into(&mut self.channels[0], &other.channels[0], 0.5);
+                add_into(&mut self.channels[0], &other.channels[1], 0.5);
+            }
+            _ => {
+                for (dst, src) in self.channels.iter_mut().zip(&other.channels) {
+                    add_into(dst, src, 1.0);
+                }
+            }
+        }
+    }
+
+    /// Average of all channels
+    pub fn mono(&self) -> Vec<f32> {
+        let scale = 1.0 / self.channel_count().max(1) as f32;
+        let mut mono = vec![0.0; self.frames()];
+        for channel in &self.channels {
+            add_into(&mut mono, channel, scale);
+        }
+        mono
+    }
+}
+
+fn add_into(dst: &mut [f32], src: &[f32], gain: f32) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d += s * gain;
+    }
+}
+
+/// Timing of the quantum being rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderQuantum {
+    pub sample_rate: f32,
+    /// Context time of the first frame
+    pub current_time: f64,
+    pub frames: usize,
+}
+
+impl RenderQuantum {
+    /// Context time of a frame within the quantum
+    pub fn frame_time(&self, frame: usize) -> f64 {
+        self.current_time + frame as f64 / self.sample_rate as f64
+    }
+}
+
+/// Processing side of an audio node
+pub trait AudioProcessor: Any + Send + std::fmt::Debug {
+    fn id(&self) -> u32;
+
+    /// Channel count of the output for a given mixed input channel count
+    fn output_channels(&self, input_channels: usize) -> usize { input_channels }
+
+    /// Produce one quantum of output. `input` is the mix of every connected
+    /// node (one silent channel when nothing is connected); `output` is
+    /// pre-zeroed with [`output_channels`](Self::output_channels) channels.
+    fn process(&mut self, input: &AudioBlock, output: &mut AudioBlock, quantum: &RenderQuantum);
+}
+
+/// Nodes of a context and their connections
+#[derive(Debug, Default)]
+pub struct AudioGraph {
+    nodes: HashMap<u32, Box<dyn AudioProcessor>>,
+    /// Sources connected into each node (including the destination)
+    inputs: HashMap<u32, Vec<u32>>,
+}
+
+impl AudioGraph {
+    pub fn new() -> Self { Self::default() }
+
+    /// Add a node, returning its id
+    pub fn add(&mut self, node: Box<dyn AudioProcessor>) -> u32 {
+        let id = node.id();
+        self.nodes.insert(id, node);
+        id
+    }
+
+    /// Remove a node and all of its connections
+    pub fn remove(&mut self, id: u32) -> Option<Box<dyn AudioProcessor>> {
+        self.inputs.remove(&id);
+        for sources in self.inputs.values_mut() {
+            sources.retain(|&s| s != id);
+        }
+        self.nodes.remove(&id)
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        id == DESTINATION || self.nodes.contains_key(&id)
+    }
+
+    /// Connect the output of `from` to the input of `to`
+    pub fn connect(&mut self, from: u32, to: u32) -> Result<(), &'static str> {
+        if !self.nodes.contains_key(&from) || !self.contains(to) {
+            return Err("InvalidAccessError: unknown node");
+        }
+        // Cycles would need a delay in the loop; they are not supported
+        if from == to || self.feeds(to, from) {
+            return Err("NotSupportedError: cycle in audio graph");
+        }
+        let sources = self.inputs.entry(to).or_default();
+        if !sources.contains(&from) {
+            sources.push(from);
+        }
+        Ok(())
+    }
+
+    /// Disconnect `from` from `to`, or from everything when `to` is `None`
+    pub fn disconnect(&mut self, from: u32, to: Option<u32>) {
+        for (&node, sources) in self.inputs.iter_mut() {
+            if to.is_none_or(|to| to == node) {
+                sources.retain(|&s| s != from);
+            }
+        }
+    }
+
+    /// Whether `from` reaches `to` through connections
+    fn feeds(&self, from: u32, to: u32) -> bool {
+        let mut stack = vec![to];
+        let mut seen = HashSet::new();
+        while let Some(node) = stack.pop() {
+            if node == from {
+                return true;
+            }
+            if seen.insert(node) {
+                stack.extend(self.inputs.get(&node).into_iter().flatten().copied());
+            }
+        }
+        false
+    }
+
+    /// Node by id and type
+    pub fn get<N: AudioProcessor>(&self, id: u32) -> Option<&N> {
+        let node: &dyn Any = self.nodes.get(&id)?.as_ref();
+        node.downcast_ref()
+    }
+
+    /// Mutable node by id and type
+    pub fn get_mut<N: AudioProcessor>(&mut self, id: u32) -> Option<&mut N> {
+        let node: &mut dyn Any = self.nodes.get_mut(&id)?.as_mut();
+        node.downcast_mut()
+    }
+
+    /// Nodes feeding the destination, each after its own inputs
+    fn render_order(&self) -> Vec<u32> {
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        // (node, inputs already pushed)
+        let mut stack: Vec<(u32, bool)> = self.inputs.get(&DESTINATION)
+            .into_iter().flatten().rev().map(|&id| (id, false)).collect();
+        while let Some((id, expanded)) = stack.pop() {
+            if expanded {
+                order.push(id);
+                continue;
+            }
+            if !visited.insert(id) {
+                continue;
+            }
+            stack.push((id, true));
+            for &source in self.inputs.get(&id).into_iter().flatten().rev() {
+                if !visited.contains(&source) {
+                    stack.push((source, false));
+                }
+            }
+        }
+        order
+    }
+
+    /// Render one quantum at the destination with `channels` channels
+    pub fn render(&mut self, channels: usize, quantum: &RenderQuantum) -> AudioBlock {
+        let mut outputs: HashMap<u32, AudioBlock> = HashMap::new();
+        for id in self.render_order() {
+            let input = self.mix_inputs(id, None, &outputs, quantum.frames);
+            let Some(node) = self.nodes.get_mut(&id) else { continue };
+            let mut output = AudioBlock::silent(node.output_channels(input.channel_count()), quantum.frames);
+            node.process(&input, &mut output, quantum);
+            outputs.insert(id, output);
+        }
+        self.mix_inputs(DESTINATION, Some(channels), &outputs, quantum.frames)
+    }
+
+    fn mix_inputs(&self, id: u32, channels: Option<usize>, outputs: &HashMap<u32, AudioBlock>, frames: usize) -> AudioBlock {
+        let sources: Vec<&AudioBlock> = self.inputs.get(&id).into_iter().flatten()
+            .filter_map(|s| outputs.get(s))
+            .collect();
+        let channels = channels.unwrap_or_else(|| sources.iter().map(|b| b.channel_count()).max().unwrap_or(1));
+        let mut mixed = AudioBlock::silent(channels, frames);
+        for block in sources {
+            mixed.mix_from(block);
+        }
+        mixed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Constant { id: u32, value: f32 }
+
+    impl AudioProcessor for Constant {
+        fn id(&self) -> u32 { self.id }
+        fn output_channels(&self, _input_channels: usize) -> usize { 1 }
+        fn process(&mut self, _input: &AudioBlock, output: &mut AudioBlock, _quantum: &RenderQuantum) {
+            output.channels[0].fill(self.value);
+        }
+    }
+
+    #[test]
+    fn test_mix_at_destination() {
+        let mut graph = AudioGraph::new();
+        graph.add(Box::new(Constant { id: 1, value: 0.25 }));
+        graph.add(Box::new(Constant { id: 2, value: 0.5 }));
+        graph.connect(1, DESTINATION).unwrap();
+        graph.connect(2, DESTINATION).unwrap();
+
+        let quantum = RenderQuantum { sample_rate: 48000.0, current_time: 0.0, frames: 4 };
+        let block = graph.render(2, &quantum);
+        assert_eq!(block.channels, vec![vec![0.75; 4], vec![0.75; 4]]);
+
+        graph.disconnect(2, None);
+        assert_eq!(graph.render(1, &quantum).channels[0], vec![0.25; 4]);
+        assert_eq!(graph.get_mut::<Constant>(1).map(|c| c.value), Some(0.25));
+    }
+
+    #[test]
+    fn test_rejects_cycles() {
+        let mut graph = AudioGraph::new();
+        graph.add(Box::new(Constant { id: 1, value: 0.0 }));
+        graph.add(Box::new(Constant { id: 2, value: 0.0 }));
+        graph.connect(1, 2).unwrap();
+        assert!(graph.connect(2, 1).is_err());
+        assert!(graph.connect(1, 9).is_err());
+    }
+}
@@ -3,14 +3,27 @@
 //! Web Audio API.
 
 pub mod context;
+pub mod graph;
+pub mod param;
+pub mod filter;
+pub mod analyser;
+pub mod convolver;
+pub mod decode;
+pub mod offline;
 pub mod spatial;
 pub mod worklet;
 
 pub use context::{
     AudioContext, AudioContextState, AudioContextOptions,
     AudioDestinationNode, OscillatorNode, OscillatorType,
-    GainNode, AnalyserNode, AudioBufferSourceNode, AudioBuffer,
-    DelayNode, BiquadFilterNode, BiquadFilterType, AudioParam,
+    GainNode, AudioBufferSourceNode, AudioBuffer,
+    DelayNode,
 };
+pub use param::AudioParam;
+pub use graph::{AudioGraph, AudioBlock, AudioProcessor, RenderQuantum, DESTINATION};
+pub use filter::{BiquadFilterNode, BiquadFilterType, DynamicsCompressorNode};
+pub use analyser::AnalyserNode;
+pub use convolver::ConvolverNode;
+pub use offline::OfflineAudioContext;
 pub use spatial::{PannerNode, StereoPannerNode, AudioListener};
 pub use worklet::{AudioWorkletNode, AudioWorkletProcessor};
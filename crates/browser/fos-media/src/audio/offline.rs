@@ -0,0 +1,165 @@
+//! Offline Audio Context
+//!
+//! Renders a graph as fast as possible into an AudioBuffer instead of an
+//! output device. Rendering is deterministic, which makes it the basis for
+//! audio tests.
+
+use super::context::{AudioBuffer, AudioContext, AudioContextOptions, AudioContextState};
+use crate::output::RENDER_QUANTUM_FRAMES;
+use std::ops::{Deref, DerefMut};
+
+/// Offline audio context
+#[derive(Debug)]
+pub struct OfflineAudioContext {
+    context: AudioContext,
+    pub number_of_channels: u32,
+    /// Length of the rendered buffer in frames
+    pub length: u32,
+}
+
+impl OfflineAudioContext {
+    pub fn new(number_of_channels: u32, length: u32, sample_rate: f32) -> Self {
+        let options = AudioContextOptions { sample_rate: Some(sample_rate), ..Default::default() };
+        Self {
+            context: AudioContext::with_options(options),
+            number_of_channels: number_of_channels.max(1),
+            length,
+        }
+    }
+
+    /// Render the whole graph; the context is closed afterwards
+    pub fn start_rendering(&mut self) -> Result<AudioBuffer, &'static str> {
+        if self.context.state != AudioContextState::Suspended {
+            return Err("InvalidStateError: rendering already started");
+        }
+        self.context.state = AudioContextState::Running;
+
+        let channels = self.number_of_channels as usize;
+        let mut data = vec![Vec::with_capacity(self.length as usize); channels];
+        let mut rendered = 0;
+        while rendered < self.length as usize {
+            let frames = RENDER_QUANTUM_FRAMES.min(self.length as usize - rendered);
+            let block = self.context.render_block(channels, frames);
+            for (dst, src) in data.iter_mut().zip(block.channels) {
+                dst.extend_from_slice(&src);
+            }
+            rendered += frames;
+        }
+
+        self.context.state = AudioContextState::Closed;
+        Ok(AudioBuffer::from_channels(data, self.context.sample_rate))
+    }
+}
+
+/// Nodes are created and connected through the underlying context
+impl Deref for OfflineAudioContext {
+    type Target = AudioContext;
+
+    fn deref(&self) -> &AudioContext { &self.context }
+}
+
+impl DerefMut for OfflineAudioContext {
+    fn deref_mut(&mut self) -> &mut AudioContext { &mut self.context }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::context::{AudioBufferSourceNode, GainNode, OscillatorType};
+    use crate::audio::filter::BiquadFilterNode;
+    use crate::audio::graph::DESTINATION;
+
+    #[test]
+    fn test_render_oscillator() {
+        let mut ctx = OfflineAudioContext::new(2, 4800, 48000.0);
+        let mut osc = ctx.create_oscillator();
+        osc.oscillator_type = OscillatorType::Square;
+        osc.frequency.value = 100.0;
+        osc.start(0.0);
+        osc.stop(0.05);
+        let mut gain = ctx.create_gain();
+        gain.gain.value = 0.5;
+
+        let osc = ctx.add_node(osc);
+        let gain = ctx.add_node(gain);
+        ctx.connect(osc, gain).unwrap();
+        ctx.connect(gain, DESTINATION).unwrap();
+
+        let buffer = ctx.start_rendering().unwrap();
+        assert_eq!((buffer.number_of_channels, buffer.length), (2, 4800));
+        let left = buffer.get_channel_data(0).unwrap();
+        assert_eq!(left[0], 0.5);
+        assert_eq!(left[300], -0.5);
+        // Stopped at 50 ms
+        assert_eq!(left[2410], 0.0);
+        assert_eq!(buffer.get_channel_data(1).unwrap(), left);
+        assert_eq!(ctx.state, AudioContextState::Closed);
+        assert!(ctx.start_rendering().is_err());
+        assert!(ctx.node::<GainNode>(gain).is_some());
+    }
+
+    #[test]
+    fn test_render_gain_automation() {
+        let mut ctx = OfflineAudioContext::new(1, 1000, 1000.0);
+        let mut source = ctx.create_buffer_source();
+        source.buffer = Some(AudioBuffer::from_channels(vec![vec![1.0; 1000]], 1000.0));
+        source.start(0.0, 0.0, None);
+        let mut gain = ctx.create_gain();
+        gain.gain.set_value_at_time(0.0, 0.0).unwrap();
+        gain.gain.linear_ramp_to_value_at_time(1.0, 0.5).unwrap();
+        gain.gain.set_target_at_time(0.0, 0.7, 0.05).unwrap();
+
+        let source = ctx.add_node(source);
+        let gain = ctx.add_node(gain);
+        ctx.connect(source, gain).unwrap();
+        ctx.connect(gain, DESTINATION).unwrap();
+
+        let buffer = ctx.start_rendering().unwrap();
+        let data = buffer.get_channel_data(0).unwrap();
+        assert_eq!(data[0], 0.0);
+        assert!((data[250] - 0.5).abs() < 1e-6);
+        assert_eq!(data[600], 1.0);
+        assert!((data[750] - (-1.0f32).exp()).abs() < 1e-5);
+        // The param reports the value of the last rendered quantum
+        let last = ctx.node::<GainNode>(gain).unwrap().gain.value;
+        assert!(last > 0.0 && last < 0.05);
+    }
+
+    #[test]
+    fn test_buffer_source_resampling() {
+        // A 24 kHz ramp played in a 48 kHz context is interpolated
+        let mut ctx = OfflineAudioContext::new(1, 256, 48000.0);
+        let ramp: Vec<f32> = (0..64).map(|i| i as f32).collect();
+        let mut source = ctx.create_buffer_source();
+        source.buffer = Some(AudioBuffer::from_channels(vec![ramp], 24000.0));
+        source.start(0.0, 0.0, None);
+        let source = ctx.add_node(source);
+        ctx.connect(source, DESTINATION).unwrap();
+
+        let buffer = ctx.start_rendering().unwrap();
+        let data = buffer.get_channel_data(0).unwrap();
+        assert_eq!(&data[..4], &[0.0, 0.5, 1.0, 1.5]);
+        assert_eq!(data[200], 0.0);
+        assert!(ctx.node::<AudioBufferSourceNode>(source).unwrap().has_ended());
+    }
+
+    #[test]
+    fn test_looping_source_through_filter() {
+        let mut ctx = OfflineAudioContext::new(1, 1024, 48000.0);
+        let mut source = ctx.create_buffer_source();
+        source.buffer = Some(AudioBuffer::from_channels(vec![vec![1.0; 16]], 48000.0));
+        source.loop_ = true;
+        source.start(0.0, 0.0, None);
+        let mut filter: BiquadFilterNode = ctx.create_biquad_filter();
+        filter.frequency.value = 1000.0;
+
+        let source = ctx.add_node(source);
+        let filter = ctx.add_node(filter);
+        ctx.connect(source, filter).unwrap();
+        ctx.connect(filter, DESTINATION).unwrap();
+
+        // DC passes a lowpass once the filter settles
+        let buffer = ctx.start_rendering().unwrap();
+        assert!((buffer.get_channel_data(0).unwrap()[1023] - 1.0).abs() < 1e-3);
+    }
+}
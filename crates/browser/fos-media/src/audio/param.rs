@@ -0,0 +1,312 @@
+//! Audio Parameters
+//!
+//! AudioParam values and their automation timeline. Events are kept sorted
+//! by time and evaluated as specified by Web Audio: ramps run from the end of
+//! the previous event, targets approach exponentially from the value at
+//! their start, and value curves are interpolated linearly.
+
+use super::graph::RenderQuantum;
+
+/// Scheduled change of a parameter
+#[derive(Debug, Clone, PartialEq)]
+enum AutomationEvent {
+    SetValue { value: f64, time: f64 },
+    LinearRamp { value: f64, time: f64 },
+    ExponentialRamp { value: f64, time: f64 },
+    SetTarget { target: f64, time: f64, time_constant: f64 },
+    ValueCurve { values: Vec<f64>, time: f64, duration: f64 },
+}
+
+impl AutomationEvent {
+    /// Time the event is ordered by (the end time for ramps)
+    fn time(&self) -> f64 {
+        match *self {
+            Self::SetValue { time, .. }
+            | Self::LinearRamp { time, .. }
+            | Self::ExponentialRamp { time, .. }
+            | Self::SetTarget { time, .. }
+            | Self::ValueCurve { time, .. } => time,
+        }
+    }
+}
+
+/// Audio parameter
+#[derive(Debug, Clone)]
+pub struct AudioParam {
+    /// Value used while nothing is scheduled; updated to the computed value
+    /// every quantum the owning node renders
+    pub value: f64,
+    pub default_value: f64,
+    pub min_value: f64,
+    pub max_value: f64,
+    events: Vec<AutomationEvent>,
+}
+
+impl AudioParam {
+    pub fn new(default: f64) -> Self {
+        Self::with_range(default, f64::MIN, f64::MAX)
+    }
+
+    /// Parameter with a nominal range; computed values are clamped to it
+    pub fn with_range(default: f64, min: f64, max: f64) -> Self {
+        Self {
+            value: default,
+            default_value: default,
+            min_value: min,
+            max_value: max,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn set_value_at_time(&mut self, value: f64, start_time: f64) -> Result<(), &'static str> {
+        check_time(start_time)?;
+        self.insert(AutomationEvent::SetValue { value, time: start_time })
+    }
+
+    pub fn linear_ramp_to_value_at_time(&mut self, value: f64, end_time: f64) -> Result<(), &'static str> {
+        check_time(end_time)?;
+        self.insert(AutomationEvent::LinearRamp { value, time: end_time })
+    }
+
+    pub fn exponential_ramp_to_value_at_time(&mut self, value: f64, end_time: f64) -> Result<(), &'static str> {
+        check_time(end_time)?;
+        if value == 0.0 || !value.is_finite() {
+            return Err("RangeError: exponential ramp target must be non-zero");
+        }
+        self.insert(AutomationEvent::ExponentialRamp { value, time: end_time })
+    }
+
+    /// Approach `target` from `start_time`, reaching 1 - 1/e of the way
+    /// after each `time_constant` seconds
+    pub fn set_target_at_time(&mut self, target: f64, start_time: f64, time_constant: f64) -> Result<(), &'static str> {
+        check_time(start_time)?;
+        if !time_constant.is_finite() || time_constant < 0.0 {
+            return Err("RangeError: time constant must be non-negative");
+        }
+        self.insert(AutomationEvent::SetTarget { target, time: start_time, time_constant })
+    }
+
+    /// Follow `values`, spread evenly over `duration` seconds
+    pub fn set_value_curve_at_time(&mut self, values: &[f64], start_time: f64, duration: f64) -> Result<(), &'static str> {
+        check_time(start_time)?;
+        if values.len() < 2 {
+            return Err("InvalidStateError: value curve needs at least two values");
+        }
+        if !duration.is_finite() || duration <= 0.0 {
+            return Err("RangeError: curve duration must be positive");
+        }
+        let end = start_time + duration;
+        let overlaps = self.events.iter().any(|event| match *event {
+            AutomationEvent::ValueCurve { time, duration, .. } => time < end && start_time < time + duration,
+            ref other => (start_time..end).contains(&other.time()),
+        });
+        if overlaps {
+            return Err("NotSupportedError: value curve overlaps scheduled events");
+        }
+        self.insert(AutomationEvent::ValueCurve { values: values.to_vec(), time: start_time, duration })
+    }
+
+    /// Remove every event at or after `cancel_time`
+    pub fn cancel_scheduled_values(&mut self, cancel_time: f64) -> Result<(), &'static str> {
+        check_time(cancel_time)?;
+        let keep = self.events.partition_point(|e| e.time() < cancel_time);
+        self.events.truncate(keep);
+        Ok(())
+    }
+
+    /// Remove every event after `cancel_time` and hold the value the
+    /// timeline has at that time; a ramp in progress is cut short there
+    pub fn cancel_and_hold_at_time(&mut self, cancel_time: f64) -> Result<(), &'static str> {
+        check_time(cancel_time)?;
+        let held = self.value_at(cancel_time);
+        let keep = self.events.partition_point(|e| e.time() < cancel_time);
+        let hold = match self.events.get(keep) {
+            Some(AutomationEvent::LinearRamp { .. }) => AutomationEvent::LinearRamp { value: held, time: cancel_time },
+            Some(AutomationEvent::ExponentialRamp { .. }) if held != 0.0 => {
+                AutomationEvent::ExponentialRamp { value: held, time: cancel_time }
+            }
+            _ => AutomationEvent::SetValue { value: held, time: cancel_time },
+        };
+        self.events.truncate(keep);
+        self.events.push(hold);
+        Ok(())
+    }
+
+    /// Whether any automation is scheduled
+    pub fn is_automated(&self) -> bool {
+        !self.events.is_empty()
+    }
+
+    fn insert(&mut self, event: AutomationEvent) -> Result<(), &'static str> {
+        let time = event.time();
+        let in_curve = self.events.iter().any(|e| match *e {
+            AutomationEvent::ValueCurve { time: start, duration, .. } => start < time && time < start + duration,
+            _ => false,
+        });
+        if in_curve {
+            return Err("NotSupportedError: event falls inside a value curve");
+        }
+        // The value before the first event is the one set when scheduling began
+        if self.events.is_empty() {
+            self.events.push(AutomationEvent::SetValue { value: self.value, time: 0.0 });
+        }
+        let index = self.events.partition_point(|e| e.time() <= time);
+        self.events.insert(index, event);
+        Ok(())
+    }
+
+    /// Computed value at context time `time`
+    pub fn value_at(&self, time: f64) -> f64 {
+        if self.events.is_empty() {
+            return self.value;
+        }
+        // Start of the segment being evaluated and, after a SetTarget, the
+        // value it approaches
+        let (mut start_time, mut start_value) = (0.0, self.value);
+        let mut target: Option<(f64, f64)> = None;
+        let hold = |t: f64, start_time: f64, start_value: f64, target: Option<(f64, f64)>| match target {
+            Some((target, tc)) if tc > 0.0 => target + (start_value - target) * (-(t - start_time) / tc).exp(),
+            Some((target, _)) => target,
+            None => start_value,
+        };
+
+        for (i, event) in self.events.iter().enumerate() {
+            match *event {
+                AutomationEvent::SetValue { value, time: at } => {
+                    if time < at {
+                        return self.clamp(hold(time, start_time, start_value, target));
+                    }
+                    (start_time, start_value, target) = (at, value, None);
+                }
+                AutomationEvent::LinearRamp { value, time: end } => {
+                    if time < end {
+                        let progress = (time - start_time) / (end - start_time);
+                        return self.clamp(start_value + (value - start_value) * progress);
+                    }
+                    (start_time, start_value, target) = (end, value, None);
+                }
+                AutomationEvent::ExponentialRamp { value, time: end } => {
+                    if time < end {
+                        // Ramps across zero or between signs hold the start value
+                        if start_value * value <= 0.0 {
+                            return self.clamp(start_value);
+                        }
+                        let progress = (time - start_time) / (end - start_time);
+                        return self.clamp(start_value * (value / start_value).powf(progress));
+                    }
+                    (start_time, start_value, target) = (end, value, None);
+                }
+                AutomationEvent::SetTarget { target: goal, time: at, time_constant } => {
+                    if time < at {
+                        return self.clamp(hold(time, start_time, start_value, target));
+                    }
+                    start_value = hold(at, start_time, start_value, target);
+                    start_time = at;
+                    target = Some((goal, time_constant));
+                }
+                AutomationEvent::ValueCurve { ref values, time: at, duration } => {
+                    if time < at {
+                        return self.clamp(hold(time, start_time, start_value, target));
+                    }
+                    // A cancel-and-hold inside the curve ends it early
+                    let end = self.events.get(i + 1).map_or(at + duration, |next| next.time().min(at + duration));
+                    let sample = |t: f64| {
+                        let position = ((t - at) / duration * (values.len() - 1) as f64).max(0.0);
+                        let k = position.floor() as usize;
+                        match (values.get(k), values.get(k + 1)) {
+                            (Some(&a), Some(&b)) => a + (b - a) * (position - k as f64),
+                            _ => values[values.len() - 1],
+                        }
+                    };
+                    if time < end {
+                        return self.clamp(sample(time));
+                    }
+                    (start_time, start_value, target) = (end, sample(end), None);
+                }
+            }
+        }
+        self.clamp(hold(time, start_time, start_value, target))
+    }
+
+    fn clamp(&self, value: f64) -> f64 {
+        value.clamp(self.min_value, self.max_value)
+    }
+
+    /// Value for a whole quantum (k-rate), taken at its first frame
+    pub(crate) fn k_rate(&mut self, quantum: &RenderQuantum) -> f64 {
+        self.value = self.value_at(quantum.current_time);
+        self.value
+    }
+
+    /// Value for every frame of a quantum (a-rate)
+    pub(crate) fn a_rate(&mut self, quantum: &RenderQuantum) -> Vec<f64> {
+        if !self.is_automated() {
+            return vec![self.clamp(self.value); quantum.frames];
+        }
+        let values: Vec<f64> = (0..quantum.frames).map(|i| self.value_at(quantum.frame_time(i))).collect();
+        self.value = values.first().copied().unwrap_or(self.value);
+        values
+    }
+}
+
+fn check_time(time: f64) -> Result<(), &'static str> {
+    if time >= 0.0 && time.is_finite() {
+        Ok(())
+    } else {
+        Err("RangeError: time must be finite and non-negative")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_ramps() {
+        let mut param = AudioParam::new(1.0);
+        param.set_value_at_time(0.0, 1.0).unwrap();
+        param.linear_ramp_to_value_at_time(1.0, 2.0).unwrap();
+        param.exponential_ramp_to_value_at_time(4.0, 4.0).unwrap();
+        assert_eq!(param.value_at(0.5), 1.0);
+        assert!(close(param.value_at(1.5), 0.5));
+        assert!(close(param.value_at(3.0), 2.0));
+        assert_eq!(param.value_at(10.0), 4.0);
+        assert!(param.exponential_ramp_to_value_at_time(0.0, 5.0).is_err());
+        assert!(param.set_value_at_time(1.0, -1.0).is_err());
+    }
+
+    #[test]
+    fn test_target_and_curve() {
+        let mut param = AudioParam::new(0.0);
+        param.set_target_at_time(1.0, 1.0, 0.5).unwrap();
+        assert_eq!(param.value_at(1.0), 0.0);
+        assert!(close(param.value_at(1.5), 1.0 - (-1.0f64).exp()));
+
+        param.set_value_curve_at_time(&[0.0, 2.0, 4.0], 3.0, 2.0).unwrap();
+        assert!(close(param.value_at(3.5), 1.0));
+        assert!(close(param.value_at(4.5), 3.0));
+        assert_eq!(param.value_at(6.0), 4.0);
+        assert!(param.set_value_at_time(0.0, 4.0).is_err());
+        assert!(param.set_value_curve_at_time(&[0.0, 1.0], 4.5, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_cancel_and_hold() {
+        let mut param = AudioParam::new(0.0);
+        param.linear_ramp_to_value_at_time(10.0, 10.0).unwrap();
+        param.set_value_at_time(-1.0, 12.0).unwrap();
+        param.cancel_and_hold_at_time(5.0).unwrap();
+        assert!(close(param.value_at(2.5), 2.5));
+        assert!(close(param.value_at(11.0), 5.0));
+        assert!(close(param.value_at(20.0), 5.0));
+
+        param.cancel_scheduled_values(0.0).unwrap();
+        assert!(!param.is_automated());
+        param.value = 3.0;
+        assert_eq!(param.value_at(1.0), 3.0);
+    }
+}
@@ -1,8 +1,11 @@
 //! Spatial Audio
 //!
-//! PannerNode and AudioListener for 3D audio.
+//! PannerNode and AudioListener for 3D audio. Both panners use the
+//! equal-power panning law; HRTF is rendered as equal-power too.
 
-use super::context::AudioParam;
+use super::param::AudioParam;
+use super::graph::{AudioBlock, AudioProcessor, RenderQuantum};
+use std::f64::consts::FRAC_PI_2;
 
 /// Audio listener (represents the listener in 3D space)
 #[derive(Debug, Clone)]
@@ -105,6 +108,66 @@ impl PannerNode {
         self.orientation_y.value = y;
         self.orientation_z.value = z;
     }
+    
+    /// Distance from the listener at the origin
+    fn distance(&self) -> f64 {
+        let (x, y, z) = (self.position_x.value, self.position_y.value, self.position_z.value);
+        (x * x + y * y + z * z).sqrt()
+    }
+    
+    /// Attenuation for the current distance
+    pub fn distance_gain(&self) -> f64 {
+        let (reference, max) = (self.ref_distance, self.max_distance);
+        let rolloff = self.rolloff_factor;
+        match self.distance_model {
+            DistanceModel::Linear => {
+                let d = self.distance().clamp(reference, max.max(reference));
+                let range = (max - reference).max(f64::EPSILON);
+                1.0 - rolloff.min(1.0) * (d - reference) / range
+            }
+            DistanceModel::Inverse => {
+                let d = self.distance().max(reference);
+                reference / (reference + rolloff * (d - reference)).max(f64::EPSILON)
+            }
+            DistanceModel::Exponential => {
+                let d = self.distance().max(reference);
+                (d / reference.max(f64::EPSILON)).powf(-rolloff)
+            }
+        }
+    }
+    
+    /// Azimuth in degrees, folded into [-90, 90], for a listener at the
+    /// origin facing -z
+    fn azimuth(&self) -> f64 {
+        let (x, z) = (self.position_x.value, self.position_z.value);
+        if x == 0.0 && z == 0.0 {
+            return 0.0;
+        }
+        let azimuth = x.atan2(-z).to_degrees();
+        if azimuth > 90.0 {
+            180.0 - azimuth
+        } else if azimuth < -90.0 {
+            -180.0 - azimuth
+        } else {
+            azimuth
+        }
+    }
+}
+
+impl AudioProcessor for PannerNode {
+    fn id(&self) -> u32 { self.id }
+    
+    fn output_channels(&self, _input_channels: usize) -> usize { 2 }
+    
+    fn process(&mut self, input: &AudioBlock, output: &mut AudioBlock, quantum: &RenderQuantum) {
+        for param in [
+            &mut self.position_x, &mut self.position_y, &mut self.position_z,
+            &mut self.orientation_x, &mut self.orientation_y, &mut self.orientation_z,
+        ] {
+            param.k_rate(quantum);
+        }
+        equal_power(input, output, self.azimuth() / 90.0, self.distance_gain() as f32);
+    }
 }
 
 /// Stereo panner node (simpler alternative)
@@ -118,8 +181,47 @@ impl StereoPannerNode {
     pub fn new(id: u32) -> Self {
         Self {
             id,
-            pan: AudioParam::new(0.0), // -1.0 = left, 0 = center, 1.0 = right
+            pan: AudioParam::with_range(0.0, -1.0, 1.0), // -1.0 = left, 0 = center, 1.0 = right
+        }
+    }
+}
+
+impl AudioProcessor for StereoPannerNode {
+    fn id(&self) -> u32 { self.id }
+    
+    fn output_channels(&self, _input_channels: usize) -> usize { 2 }
+    
+    fn process(&mut self, input: &AudioBlock, output: &mut AudioBlock, quantum: &RenderQuantum) {
+        let pan = self.pan.k_rate(quantum);
+        equal_power(input, output, pan.clamp(-1.0, 1.0), 1.0);
+    }
+}
+
+/// Equal-power pan of a mono or stereo input into stereo `output`;
+/// `pan` is -1 (left) to 1 (right)
+fn equal_power(input: &AudioBlock, output: &mut AudioBlock, pan: f64, gain: f32) {
+    let (left, right) = output.channels.split_at_mut(1);
+    let (left, right) = (&mut left[0], &mut right[0]);
+    if input.channel_count() == 1 {
+        let x = (pan + 1.0) / 2.0;
+        let (gl, gr) = (((x * FRAC_PI_2).cos() as f32) * gain, ((x * FRAC_PI_2).sin() as f32) * gain);
+        for (i, &s) in input.channels[0].iter().enumerate() {
+            left[i] = s * gl;
+            right[i] = s * gr;
         }
+        return;
+    }
+    let (in_l, in_r) = (&input.channels[0], &input.channels[1]);
+    let x = if pan <= 0.0 { pan + 1.0 } else { pan };
+    let (cos, sin) = ((x * FRAC_PI_2).cos() as f32, (x * FRAC_PI_2).sin() as f32);
+    for i in 0..left.len() {
+        let (l, r) = if pan <= 0.0 {
+            (in_l[i] + in_r[i] * cos, in_r[i] * sin)
+        } else {
+            (in_l[i] * cos, in_r[i] + in_l[i] * sin)
+        };
+        left[i] = l * gain;
+        right[i] = r * gain;
     }
 }
 
@@ -141,4 +243,26 @@ mod tests {
         let panner = StereoPannerNode::new(1);
         assert_eq!(panner.pan.value, 0.0);
     }
+    
+    #[test]
+    fn test_pan_hard_right() {
+        let mut panner = StereoPannerNode::new(1);
+        panner.pan.value = 1.0;
+        let input = AudioBlock { channels: vec![vec![1.0; 4]] };
+        let mut output = AudioBlock::silent(2, 4);
+        let quantum = RenderQuantum { sample_rate: 48000.0, current_time: 0.0, frames: 4 };
+        panner.process(&input, &mut output, &quantum);
+        assert!(output.channels[0][0].abs() < 1e-6);
+        assert!((output.channels[1][0] - 1.0).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn test_distance_attenuation() {
+        let mut panner = PannerNode::new(1);
+        panner.set_position(0.0, 0.0, -4.0);
+        assert!((panner.distance_gain() - 0.25).abs() < 1e-9);
+        panner.distance_model = DistanceModel::Linear;
+        panner.max_distance = 5.0;
+        assert!((panner.distance_gain() - 0.25).abs() < 1e-9);
+    }
 }
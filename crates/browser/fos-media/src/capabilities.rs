@@ -0,0 +1,302 @@
+//! Media Capabilities
+//!
+//! `navigator.mediaCapabilities.decodingInfo()` and `encodingInfo()`.
+//! Answers come from the [`CodecRegistry`], whose limits already include
+//! the hardware decoders found on this machine: a configuration is smooth
+//! when it stays within what the decoder (or encoder) keeps up with, and
+//! power efficient when a hardware codec handles it.
+
+use crate::codecs::{CodecInfo, CodecRegistry, CodecType};
+use crate::containers::{self, CodecId, ContainerFormat};
+
+/// `MediaDecodingType`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MediaDecodingType {
+    #[default]
+    File,
+    MediaSource,
+    WebRtc,
+}
+
+/// `MediaEncodingType`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MediaEncodingType {
+    #[default]
+    Record,
+    WebRtc,
+}
+
+/// `VideoConfiguration`
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoConfiguration {
+    pub content_type: String,
+    pub width: u32,
+    pub height: u32,
+    pub bitrate: u64,
+    pub framerate: f64,
+}
+
+/// `AudioConfiguration`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioConfiguration {
+    pub content_type: String,
+    pub channels: Option<String>,
+    pub bitrate: Option<u64>,
+    pub samplerate: Option<u32>,
+}
+
+/// `MediaDecodingConfiguration`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaDecodingConfiguration {
+    pub decoding_type: MediaDecodingType,
+    pub video: Option<VideoConfiguration>,
+    pub audio: Option<AudioConfiguration>,
+}
+
+/// `MediaEncodingConfiguration`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaEncodingConfiguration {
+    pub encoding_type: MediaEncodingType,
+    pub video: Option<VideoConfiguration>,
+    pub audio: Option<AudioConfiguration>,
+}
+
+/// `MediaCapabilitiesInfo`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MediaCapabilitiesInfo {
+    pub supported: bool,
+    pub smooth: bool,
+    pub power_efficient: bool,
+}
+
+impl MediaCapabilitiesInfo {
+    const UNSUPPORTED: Self = Self { supported: false, smooth: false, power_efficient: false };
+}
+
+/// Media capabilities error; rejects the promise with a `TypeError`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MediaCapabilitiesError {
+    #[error("TypeError: {0}")]
+    TypeError(String),
+}
+
+/// Pixels per second a software codec sustains for real-time playback
+fn software_pixel_rate(codec: CodecType) -> f64 {
+    const P1080: f64 = 1920.0 * 1080.0;
+    const P720: f64 = 1280.0 * 720.0;
+    match codec {
+        CodecType::H264 | CodecType::VP8 => P1080 * 60.0,
+        CodecType::VP9 => P1080 * 30.0,
+        _ => P720 * 30.0,
+    }
+}
+
+/// `navigator.mediaCapabilities`
+#[derive(Debug, Clone)]
+pub struct MediaCapabilities {
+    registry: CodecRegistry,
+}
+
+impl Default for MediaCapabilities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MediaCapabilities {
+    pub fn new() -> Self {
+        Self::with_registry(CodecRegistry::new())
+    }
+
+    pub fn with_registry(registry: CodecRegistry) -> Self {
+        Self { registry }
+    }
+
+    /// `decodingInfo()`
+    pub fn decoding_info(&self, config: &MediaDecodingConfiguration) -> Result<MediaCapabilitiesInfo, MediaCapabilitiesError> {
+        let webrtc = config.decoding_type == MediaDecodingType::WebRtc;
+        let parsed = parse_configuration(config.video.as_ref(), config.audio.as_ref(), webrtc)?;
+        let container_ok = |format: Option<ContainerFormat>| match config.decoding_type {
+            MediaDecodingType::File => true,
+            MediaDecodingType::MediaSource => matches!(format, Some(ContainerFormat::Mp4 | ContainerFormat::WebM)),
+            MediaDecodingType::WebRtc => format.is_none(),
+        };
+        Ok(capabilities_info(config.video.as_ref(), config.audio.is_some(), parsed, container_ok, |codec| self.registry.decoder_info(codec)))
+    }
+
+    /// `encodingInfo()`
+    pub fn encoding_info(&self, config: &MediaEncodingConfiguration) -> Result<MediaCapabilitiesInfo, MediaCapabilitiesError> {
+        let webrtc = config.encoding_type == MediaEncodingType::WebRtc;
+        let parsed = parse_configuration(config.video.as_ref(), config.audio.as_ref(), webrtc)?;
+        // MediaRecorder writes WebM only
+        let container_ok = |format: Option<ContainerFormat>| match config.encoding_type {
+            MediaEncodingType::Record => format == Some(ContainerFormat::WebM),
+            MediaEncodingType::WebRtc => format.is_none(),
+        };
+        Ok(capabilities_info(config.video.as_ref(), config.audio.is_some(), parsed, container_ok, |codec| self.registry.encoder_info(codec)))
+    }
+}
+
+/// Container and codec named by a content type; `None` when unsupported
+type ParsedType = Option<(Option<ContainerFormat>, CodecType)>;
+
+/// Combined capabilities of the video and audio parts of a configuration
+fn capabilities_info<'a>(
+    video: Option<&VideoConfiguration>,
+    has_audio: bool,
+    (video_type, audio_type): (ParsedType, ParsedType),
+    container_ok: impl Fn(Option<ContainerFormat>) -> bool,
+    codec_info: impl Fn(CodecType) -> Option<&'a CodecInfo>,
+) -> MediaCapabilitiesInfo {
+    let mut info = MediaCapabilitiesInfo { supported: true, smooth: true, power_efficient: true };
+    if let Some(video) = video {
+        info = match video_type.filter(|&(format, _)| container_ok(format)).and_then(|(_, codec)| codec_info(codec)) {
+            Some(codec) => video_info(codec, video),
+            None => MediaCapabilitiesInfo::UNSUPPORTED,
+        };
+    }
+    // Audio is cheap enough to stay smooth and efficient in software
+    let audio_supported = audio_type.is_some_and(|(format, codec)| container_ok(format) && codec_info(codec).is_some());
+    if has_audio && !audio_supported {
+        info = MediaCapabilitiesInfo::UNSUPPORTED;
+    }
+    info
+}
+
+/// Capabilities of a video configuration against a codec's limits
+fn video_info(codec: &CodecInfo, video: &VideoConfiguration) -> MediaCapabilitiesInfo {
+    if video.width > codec.max_width || video.height > codec.max_height {
+        return MediaCapabilitiesInfo::UNSUPPORTED;
+    }
+    let pixel_rate = video.width as f64 * video.height as f64 * video.framerate;
+    let smooth = if codec.hardware_accelerated {
+        video.framerate <= codec.max_frame_rate
+    } else {
+        pixel_rate <= software_pixel_rate(codec.codec)
+    };
+    MediaCapabilitiesInfo { supported: true, smooth, power_efficient: codec.hardware_accelerated }
+}
+
+/// Container and codec of each configuration, rejecting invalid ones with a
+/// `TypeError` as the spec requires
+fn parse_configuration(
+    video: Option<&VideoConfiguration>,
+    audio: Option<&AudioConfiguration>,
+    webrtc: bool,
+) -> Result<(ParsedType, ParsedType), MediaCapabilitiesError> {
+    if video.is_none() && audio.is_none() {
+        return Err(MediaCapabilitiesError::TypeError("audio or video configuration required".into()));
+    }
+    let video = match video {
+        Some(video) => {
+            if video.width == 0 || video.height == 0 || !(video.framerate.is_finite() && video.framerate > 0.0) {
+                return Err(MediaCapabilitiesError::TypeError("invalid video dimensions or framerate".into()));
+            }
+            parse_content_type(&video.content_type, "video/", webrtc)?
+        }
+        None => None,
+    };
+    let audio = match audio {
+        Some(audio) => parse_content_type(&audio.content_type, "audio/", webrtc)?,
+        None => None,
+    };
+    Ok((video, audio))
+}
+
+/// Container and codec named by a content type. A valid type that names an
+/// unknown container or codec parses to `None` (unsupported); only
+/// malformed types are errors.
+fn parse_content_type(content_type: &str, kind: &str, webrtc: bool) -> Result<ParsedType, MediaCapabilitiesError> {
+    let invalid = || MediaCapabilitiesError::TypeError(format!("invalid {} contentType: {content_type}", kind.trim_end_matches('/')));
+    let (essence, codecs) = containers::parse_mime(content_type);
+    let Some(subtype) = essence.strip_prefix(kind).filter(|s| !s.is_empty() && !s.contains('/')) else {
+        return Err(invalid());
+    };
+
+    if webrtc {
+        // RTP payload names: video/VP8, audio/opus, ...
+        let codec = match subtype {
+            "h264" => Some(CodecId::H264),
+            "h265" => Some(CodecId::H265),
+            "vp8" => Some(CodecId::Vp8),
+            "vp9" => Some(CodecId::Vp9),
+            "av1" => Some(CodecId::Av1),
+            "opus" => Some(CodecId::Opus),
+            _ => None,
+        };
+        return Ok(codec.and_then(containers::codec_type).map(|codec| (None, codec)));
+    }
+
+    // Container types have to name exactly one codec
+    let [codec] = codecs.as_slice() else { return Err(invalid()) };
+    let Some(format) = containers::format_for_mime(&essence) else { return Ok(None) };
+    let codec = containers::codec_from_string(codec)
+        .filter(|&id| format.can_carry(id) && id.is_video() == (kind == "video/"));
+    Ok(codec.and_then(containers::codec_type).map(|codec| (Some(format), codec)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(content_type: &str, width: u32, height: u32, framerate: f64) -> VideoConfiguration {
+        VideoConfiguration { content_type: content_type.into(), width, height, bitrate: 2_000_000, framerate }
+    }
+
+    fn info(codec: CodecType, hardware_accelerated: bool, max_width: u32, max_height: u32) -> CodecInfo {
+        CodecInfo { codec, hardware_accelerated, max_width, max_height, max_frame_rate: 60.0 }
+    }
+
+    #[test]
+    fn test_decoding_info() {
+        let mut registry = CodecRegistry::default();
+        registry.register_decoder(info(CodecType::H264, true, 4096, 2304));
+        registry.register_decoder(info(CodecType::VP9, false, 4096, 2160));
+        registry.register_decoder(info(CodecType::VP8, false, 4096, 2160));
+        registry.register_decoder(info(CodecType::Opus, false, 0, 0));
+        let capabilities = MediaCapabilities::with_registry(registry);
+        let decode = |video: VideoConfiguration| capabilities.decoding_info(&MediaDecodingConfiguration {
+            decoding_type: MediaDecodingType::MediaSource,
+            video: Some(video),
+            audio: Some(AudioConfiguration { content_type: "audio/webm; codecs=opus".into(), ..Default::default() }),
+        });
+
+        // Hardware H.264 is smooth and power efficient at 4K; software VP9 only keeps up to 1080p
+        let h264 = decode(video("video/mp4; codecs=\"avc1.640028\"", 3840, 2160, 60.0)).unwrap();
+        assert_eq!(h264, MediaCapabilitiesInfo { supported: true, smooth: true, power_efficient: true });
+        let vp9 = |w, h| decode(video("video/webm; codecs=\"vp09.00.10.08\"", w, h, 30.0)).unwrap();
+        assert_eq!(vp9(1920, 1080), MediaCapabilitiesInfo { supported: true, smooth: true, power_efficient: false });
+        assert_eq!(vp9(3840, 2160), MediaCapabilitiesInfo { supported: true, smooth: false, power_efficient: false });
+        assert_eq!(vp9(8192, 4320), MediaCapabilitiesInfo::UNSUPPORTED);
+        assert!(!decode(video("video/mp4; codecs=\"vp8\"", 640, 360, 30.0)).unwrap().supported);
+
+        assert!(decode(video("video/mp4", 640, 360, 30.0)).is_err());
+        assert!(decode(video("video/webm; codecs=\"vp8, vp9\"", 640, 360, 30.0)).is_err());
+        assert!(decode(video("audio/webm; codecs=\"vp8\"", 640, 360, 30.0)).is_err());
+        assert!(capabilities.decoding_info(&MediaDecodingConfiguration::default()).is_err());
+    }
+
+    #[test]
+    fn test_encoding_info() {
+        let mut registry = CodecRegistry::default();
+        registry.register_encoder(info(CodecType::VP8, false, 4096, 2160));
+        registry.register_encoder(info(CodecType::Opus, false, 0, 0));
+        let capabilities = MediaCapabilities::with_registry(registry);
+        let record = |video: Option<VideoConfiguration>, audio: &str| capabilities.encoding_info(&MediaEncodingConfiguration {
+            encoding_type: MediaEncodingType::Record,
+            video,
+            audio: Some(AudioConfiguration { content_type: audio.into(), ..Default::default() }),
+        }).unwrap();
+
+        let info = record(Some(video("video/webm; codecs=vp8", 1280, 720, 30.0)), "audio/webm; codecs=opus");
+        assert_eq!(info, MediaCapabilitiesInfo { supported: true, smooth: true, power_efficient: false });
+        assert!(!record(None, "audio/mp4; codecs=mp4a.40.2").supported);
+
+        let webrtc = capabilities.encoding_info(&MediaEncodingConfiguration {
+            encoding_type: MediaEncodingType::WebRtc,
+            video: None,
+            audio: Some(AudioConfiguration { content_type: "audio/opus".into(), ..Default::default() }),
+        }).unwrap();
+        assert!(webrtc.supported && webrtc.power_efficient);
+    }
+}
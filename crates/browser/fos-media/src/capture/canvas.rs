@@ -0,0 +1,131 @@
+//! Canvas Capture
+//!
+//! `canvas.captureStream(frameRate)`: a video track fed with the canvas
+//! contents. Without a frame rate a frame is taken every time the canvas
+//! changes; with one, at most that many per second; with 0 only after
+//! `requestFrame()`. The browser reports changed canvases through
+//! [`CanvasCaptureTrack::canvas_changed`].
+
+use super::CapturedFrame;
+use crate::webrtc::connection::{MediaStream, MediaStreamTrack, MediaStreamTrackKind, MediaStreamTrackState};
+use std::time::Duration;
+
+/// `captureStream()` error
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CanvasCaptureError {
+    /// Negative frame rate
+    #[error("Frame rate must not be negative")]
+    NotSupported,
+
+    /// The canvas holds cross-origin content
+    #[error("Canvas is not origin-clean")]
+    Security,
+}
+
+/// CanvasCaptureMediaStreamTrack
+#[derive(Debug)]
+pub struct CanvasCaptureTrack {
+    track: MediaStreamTrack,
+    frame_rate: Option<f64>,
+    frame_requested: bool,
+    /// When `captureStream()` was called; frame timestamps count from here
+    started: Duration,
+    last_frame: Option<Duration>,
+}
+
+impl CanvasCaptureTrack {
+    /// `canvas.captureStream(frameRate)`: a stream with one video track
+    pub fn capture_stream(frame_rate: Option<f64>, origin_clean: bool, now: Duration) -> Result<(MediaStream, Self), CanvasCaptureError> {
+        if frame_rate.is_some_and(|r| r < 0.0 || r.is_nan()) {
+            return Err(CanvasCaptureError::NotSupported);
+        }
+        if !origin_clean {
+            return Err(CanvasCaptureError::Security);
+        }
+        let mut stream = MediaStream::new();
+        let track = MediaStreamTrack {
+            id: format!("{}-canvas", stream.id),
+            kind: MediaStreamTrackKind::Video,
+            label: String::new(),
+            enabled: true,
+            muted: false,
+            ready_state: MediaStreamTrackState::Live,
+        };
+        stream.add_track(track.clone());
+        // The first frame is captured as soon as the canvas is painted
+        Ok((stream, Self { track, frame_rate, frame_requested: true, started: now, last_frame: None }))
+    }
+
+    pub fn track(&self) -> &MediaStreamTrack {
+        &self.track
+    }
+
+    /// `requestFrame()`: capture the canvas at its next change, whatever
+    /// the frame rate
+    pub fn request_frame(&mut self) {
+        self.frame_requested = true;
+    }
+
+    /// `track.stop()`
+    pub fn stop(&mut self) {
+        self.track.ready_state = MediaStreamTrackState::Ended;
+    }
+
+    /// The canvas was painted and its pixels changed; returns the frame if
+    /// one is due. Frames are dropped while the track is disabled.
+    pub fn canvas_changed(&mut self, rgba: &[u8], width: u32, height: u32, now: Duration) -> Option<CapturedFrame> {
+        if self.track.ready_state == MediaStreamTrackState::Ended {
+            return None;
+        }
+        let due = self.frame_requested || match self.frame_rate {
+            None => true,
+            Some(rate) if rate > 0.0 => self.last_frame.is_none_or(|last| {
+                now.saturating_sub(last).as_secs_f64() >= 1.0 / rate
+            }),
+            Some(_) => false,
+        };
+        if !due {
+            return None;
+        }
+        self.frame_requested = false;
+        self.last_frame = Some(now);
+        if !self.track.enabled {
+            return None;
+        }
+        Some(CapturedFrame::from_rgba(rgba, width, height, now.saturating_sub(self.started)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_rate() {
+        let ms = Duration::from_millis;
+        let (stream, mut track) = CanvasCaptureTrack::capture_stream(Some(10.0), true, ms(1000)).unwrap();
+        assert_eq!(stream.get_video_tracks()[0].id, track.track().id);
+        let pixel = [255, 0, 0, 255];
+
+        let frame = track.canvas_changed(&pixel, 1, 1, ms(1010)).unwrap();
+        assert_eq!((frame.timestamp, &frame.data[..]), (ms(10), &[0, 0, 255, 255][..]));
+        assert!(track.canvas_changed(&pixel, 1, 1, ms(1050)).is_none());
+        assert!(track.canvas_changed(&pixel, 1, 1, ms(1110)).is_some());
+
+        // With a frame rate of 0 only requested frames are captured
+        let (_, mut manual) = CanvasCaptureTrack::capture_stream(Some(0.0), true, ms(0)).unwrap();
+        assert!(manual.canvas_changed(&pixel, 1, 1, ms(5)).is_some());
+        assert!(manual.canvas_changed(&pixel, 1, 1, ms(500)).is_none());
+        manual.request_frame();
+        assert!(manual.canvas_changed(&pixel, 1, 1, ms(501)).is_some());
+        manual.stop();
+        manual.request_frame();
+        assert!(manual.canvas_changed(&pixel, 1, 1, ms(502)).is_none());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(CanvasCaptureTrack::capture_stream(Some(-1.0), true, Duration::ZERO).unwrap_err(), CanvasCaptureError::NotSupported);
+        assert_eq!(CanvasCaptureTrack::capture_stream(None, false, Duration::ZERO).unwrap_err(), CanvasCaptureError::Security);
+    }
+}
@@ -0,0 +1,336 @@
+//! Camera and Microphone Devices
+//!
+//! Input devices for `getUserMedia`. A [`CaptureDeviceBackend`] lists the
+//! platform's cameras and microphones with their formats; opening one yields
+//! a [`CaptureDevice`] that is started in a single negotiated format.
+//!
+//! Backends:
+//! - Linux: V4L2 cameras, ALSA capture PCMs
+//! - macOS: AVFoundation (AVCaptureDevice)
+//! - Windows: Media Foundation (IMFSourceReader)
+//! - Null: synthetic camera and microphone (tests, headless)
+
+use super::{CaptureError, CaptureResult, CapturedFrame, FrameClock, CaptureConfig};
+use std::f64::consts::TAU;
+
+/// Kind of media device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaDeviceKind {
+    AudioInput,
+    VideoInput,
+    AudioOutput,
+}
+
+impl MediaDeviceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AudioInput => "audioinput",
+            Self::VideoInput => "videoinput",
+            Self::AudioOutput => "audiooutput",
+        }
+    }
+}
+
+/// Entry of `enumerateDevices()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaDeviceInfo {
+    pub device_id: String,
+    pub kind: MediaDeviceKind,
+    pub label: String,
+    /// Devices of one physical product (a webcam and its microphone) share a group
+    pub group_id: String,
+}
+
+/// Direction a camera faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FacingMode {
+    User,
+    Environment,
+    Left,
+    Right,
+}
+
+/// Camera format
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoFormat {
+    pub width: u32,
+    pub height: u32,
+    pub frame_rate: f64,
+}
+
+/// Microphone format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channel_count: u16,
+}
+
+/// Format a device is started in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureFormat {
+    Video(VideoFormat),
+    Audio { format: AudioFormat, echo_cancellation: bool },
+}
+
+/// What a device can deliver
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceCapabilities {
+    /// Camera formats, in the device's preference order
+    pub video_formats: Vec<VideoFormat>,
+    pub audio_formats: Vec<AudioFormat>,
+    pub facing_mode: Option<FacingMode>,
+    /// Echo cancellation can be switched on
+    pub echo_cancellation: bool,
+}
+
+/// An opened camera or microphone
+pub trait CaptureDevice: Send {
+    fn info(&self) -> &MediaDeviceInfo;
+
+    /// Start delivering in `format`; a running device is restarted
+    fn start(&mut self, format: CaptureFormat) -> CaptureResult<()>;
+
+    /// Latest camera frame, if one is due since the previous call
+    fn read_frame(&mut self) -> Option<CapturedFrame>;
+
+    /// Fill `out` with interleaved microphone samples; returns frames written
+    fn read_audio(&mut self, out: &mut [f32]) -> usize;
+
+    fn stop(&mut self);
+
+    fn is_capturing(&self) -> bool;
+}
+
+/// Platform camera and microphone access
+pub trait CaptureDeviceBackend: Send {
+    /// Backend name ("v4l2", "avfoundation", "media-foundation", "null")
+    fn name(&self) -> &'static str;
+
+    /// Cameras and microphones currently attached
+    fn enumerate_devices(&self) -> Vec<MediaDeviceInfo>;
+
+    fn capabilities(&self, device_id: &str) -> Option<DeviceCapabilities>;
+
+    /// Open a device for capture
+    fn open(&self, device_id: &str) -> CaptureResult<Box<dyn CaptureDevice>>;
+}
+
+/// Create the preferred device backend for this platform
+pub fn create_device_backend() -> Box<dyn CaptureDeviceBackend> {
+    #[cfg(target_os = "linux")]
+    {
+        if super::linux::V4l2Devices::is_available() {
+            return Box::new(super::linux::V4l2Devices::new());
+        }
+        Box::new(NullDevices::new())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Box::new(super::macos::AVFoundationDevices::new())
+    }
+    #[cfg(target_os = "windows")]
+    {
+        Box::new(super::windows::MediaFoundationDevices::new())
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        Box::new(NullDevices::new())
+    }
+}
+
+/// Formats most webcams offer
+pub(crate) fn common_camera_formats() -> Vec<VideoFormat> {
+    vec![
+        VideoFormat { width: 640, height: 480, frame_rate: 30.0 },
+        VideoFormat { width: 1280, height: 720, frame_rate: 30.0 },
+        VideoFormat { width: 1920, height: 1080, frame_rate: 15.0 },
+        VideoFormat { width: 320, height: 240, frame_rate: 30.0 },
+    ]
+}
+
+/// Formats most microphones offer
+pub(crate) fn common_microphone_formats() -> Vec<AudioFormat> {
+    vec![
+        AudioFormat { sample_rate: 48000, channel_count: 1 },
+        AudioFormat { sample_rate: 48000, channel_count: 2 },
+        AudioFormat { sample_rate: 44100, channel_count: 1 },
+        AudioFormat { sample_rate: 44100, channel_count: 2 },
+    ]
+}
+
+/// Synthetic camera: colour bars with a moving marker
+#[derive(Debug)]
+pub struct NullCamera {
+    info: MediaDeviceInfo,
+    format: Option<VideoFormat>,
+    clock: FrameClock,
+    frames: u64,
+}
+
+impl NullCamera {
+    pub fn new(info: MediaDeviceInfo) -> Self {
+        Self { info, format: None, clock: FrameClock::default(), frames: 0 }
+    }
+}
+
+impl CaptureDevice for NullCamera {
+    fn info(&self) -> &MediaDeviceInfo { &self.info }
+
+    fn start(&mut self, format: CaptureFormat) -> CaptureResult<()> {
+        let CaptureFormat::Video(format) = format else {
+            return Err(CaptureError::Backend("camera started with an audio format".into()));
+        };
+        self.format = Some(format);
+        self.clock.start(&CaptureConfig { frame_rate: format.frame_rate, ..CaptureConfig::default() });
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> Option<CapturedFrame> {
+        let format = self.format?;
+        let timestamp = self.clock.tick()?;
+        const BARS: [[u8; 4]; 7] = [
+            [0xC0, 0xC0, 0xC0, 0xFF], [0x00, 0xC0, 0xC0, 0xFF], [0xC0, 0xC0, 0x00, 0xFF], [0x00, 0xC0, 0x00, 0xFF],
+            [0xC0, 0x00, 0xC0, 0xFF], [0x00, 0x00, 0xC0, 0xFF], [0xC0, 0x00, 0x00, 0xFF],
+        ];
+        let (width, height) = (format.width, format.height);
+        let marker = (self.frames * 4 % height as u64) as u32;
+        let mut data = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let bar = if y.abs_diff(marker) < 4 { [0xFF; 4] } else { BARS[(x * 7 / width) as usize] };
+                data.extend_from_slice(&bar);
+            }
+        }
+        self.frames += 1;
+        Some(CapturedFrame { width, height, data, timestamp })
+    }
+
+    fn read_audio(&mut self, _out: &mut [f32]) -> usize { 0 }
+
+    fn stop(&mut self) {
+        self.format = None;
+        self.clock.stop();
+    }
+
+    fn is_capturing(&self) -> bool { self.format.is_some() }
+}
+
+/// Synthetic microphone: a quiet 440 Hz tone
+#[derive(Debug)]
+pub struct NullMicrophone {
+    info: MediaDeviceInfo,
+    format: Option<AudioFormat>,
+    phase: f64,
+}
+
+impl NullMicrophone {
+    pub fn new(info: MediaDeviceInfo) -> Self {
+        Self { info, format: None, phase: 0.0 }
+    }
+}
+
+impl CaptureDevice for NullMicrophone {
+    fn info(&self) -> &MediaDeviceInfo { &self.info }
+
+    fn start(&mut self, format: CaptureFormat) -> CaptureResult<()> {
+        let CaptureFormat::Audio { format, .. } = format else {
+            return Err(CaptureError::Backend("microphone started with a video format".into()));
+        };
+        self.format = Some(format);
+        self.phase = 0.0;
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> Option<CapturedFrame> { None }
+
+    fn read_audio(&mut self, out: &mut [f32]) -> usize {
+        let Some(format) = self.format else { return 0 };
+        let channels = format.channel_count.max(1) as usize;
+        let step = TAU * 440.0 / format.sample_rate as f64;
+        for frame in out.chunks_exact_mut(channels) {
+            frame.fill((self.phase.sin() * 0.1) as f32);
+            self.phase = (self.phase + step) % TAU;
+        }
+        out.len() / channels
+    }
+
+    fn stop(&mut self) { self.format = None; }
+
+    fn is_capturing(&self) -> bool { self.format.is_some() }
+}
+
+/// Backend with one synthetic camera and microphone
+#[derive(Debug, Default)]
+pub struct NullDevices;
+
+impl NullDevices {
+    pub fn new() -> Self { Self }
+}
+
+impl CaptureDeviceBackend for NullDevices {
+    fn name(&self) -> &'static str { "null" }
+
+    fn enumerate_devices(&self) -> Vec<MediaDeviceInfo> {
+        vec![
+            MediaDeviceInfo {
+                device_id: "null:camera".into(), kind: MediaDeviceKind::VideoInput,
+                label: "Test Camera".into(), group_id: "null".into(),
+            },
+            MediaDeviceInfo {
+                device_id: "null:microphone".into(), kind: MediaDeviceKind::AudioInput,
+                label: "Test Microphone".into(), group_id: "null".into(),
+            },
+        ]
+    }
+
+    fn capabilities(&self, device_id: &str) -> Option<DeviceCapabilities> {
+        match device_id {
+            "null:camera" => Some(DeviceCapabilities {
+                video_formats: common_camera_formats(),
+                facing_mode: Some(FacingMode::User),
+                ..Default::default()
+            }),
+            "null:microphone" => Some(DeviceCapabilities {
+                audio_formats: common_microphone_formats(),
+                echo_cancellation: true,
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+
+    fn open(&self, device_id: &str) -> CaptureResult<Box<dyn CaptureDevice>> {
+        let info = self.enumerate_devices().into_iter().find(|d| d.device_id == device_id)
+            .ok_or_else(|| CaptureError::SourceNotFound(device_id.to_string()))?;
+        Ok(match info.kind {
+            MediaDeviceKind::VideoInput => Box::new(NullCamera::new(info)),
+            _ => Box::new(NullMicrophone::new(info)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_devices() {
+        let backend = NullDevices::new();
+        let devices = backend.enumerate_devices();
+        assert_eq!(devices.len(), 2);
+
+        let mut camera = backend.open("null:camera").unwrap();
+        assert!(camera.start(CaptureFormat::Audio { format: common_microphone_formats()[0], echo_cancellation: false }).is_err());
+        camera.start(CaptureFormat::Video(VideoFormat { width: 320, height: 240, frame_rate: 30.0 })).unwrap();
+        let frame = camera.read_frame().unwrap();
+        assert_eq!(frame.data.len(), 320 * 240 * 4);
+
+        let mut microphone = backend.open("null:microphone").unwrap();
+        microphone.start(CaptureFormat::Audio { format: AudioFormat { sample_rate: 48000, channel_count: 2 }, echo_cancellation: true }).unwrap();
+        let mut samples = [0.0f32; 256];
+        assert_eq!(microphone.read_audio(&mut samples), 128);
+        assert_eq!(samples[2], samples[3]);
+        assert!(samples.iter().any(|&s| s != 0.0));
+        assert!(backend.open("missing").is_err());
+    }
+}
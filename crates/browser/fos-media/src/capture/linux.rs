@@ -0,0 +1,195 @@
+//! Linux Capture (PipeWire via xdg-desktop-portal, V4L2, ALSA)
+//!
+//! On Wayland (and sandboxed X11) capture goes through the portal: the
+//! ScreenCast interface shows the compositor's own chooser and hands back a
+//! PipeWire node to read frames from. The sources listed here are therefore
+//! surface kinds rather than individual monitors or windows.
+//!
+//! This is a stub implementation for the stream itself. Full implementation
+//! would call org.freedesktop.portal.ScreenCast (CreateSession,
+//! SelectSources, Start, OpenPipeWireRemote) over D-Bus and consume the node
+//! with libpipewire (pw_stream_connect, SPA_VIDEO_FORMAT_BGRx).
+//!
+//! Cameras are the V4L2 capture nodes in sysfs and microphones the ALSA
+//! capture PCMs; streaming from them is stubbed the same way.
+
+use super::device::{common_camera_formats, common_microphone_formats};
+use super::{
+    CaptureConfig, CaptureDevice, CaptureDeviceBackend, CaptureError, CaptureResult, CaptureSource,
+    CapturedFrame, DeviceCapabilities, MediaDeviceInfo, MediaDeviceKind, NullCamera, NullCapture,
+    NullMicrophone, ScreenCaptureBackend,
+};
+use crate::webrtc::screen::DisplaySurface;
+use std::path::{Path, PathBuf};
+
+/// PipeWire screen capture
+#[derive(Debug, Default)]
+pub struct PipeWireCapture {
+    // In real impl: portal session handle, pw_stream
+    node_id: Option<u32>,
+    sink: NullCapture,
+}
+
+impl PipeWireCapture {
+    pub fn new() -> Self { Self::default() }
+
+    /// PipeWire is usable when its socket exists in the runtime directory
+    pub fn is_available() -> bool {
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .map(|dir| PathBuf::from(dir).join("pipewire-0").exists())
+            .unwrap_or(false)
+    }
+}
+
+impl ScreenCaptureBackend for PipeWireCapture {
+    fn name(&self) -> &'static str { "pipewire" }
+
+    fn enumerate_sources(&self) -> Vec<CaptureSource> {
+        // SelectSources types: MONITOR = 1, WINDOW = 2
+        vec![
+            CaptureSource {
+                id: "portal:monitor".into(), surface: DisplaySurface::Monitor, name: "Entire screen".into(),
+                width: 1920, height: 1080, tab_id: None, has_audio: false,
+            },
+            CaptureSource {
+                id: "portal:window".into(), surface: DisplaySurface::Window, name: "Window".into(),
+                width: 1280, height: 720, tab_id: None, has_audio: false,
+            },
+        ]
+    }
+
+    fn start(&mut self, source: &CaptureSource, config: CaptureConfig) -> CaptureResult<()> {
+        let pattern = match source.id.as_str() {
+            "portal:monitor" => "monitor:0",
+            "portal:window" => "window:0",
+            other => return Err(CaptureError::SourceNotFound(other.to_string())),
+        };
+        // Real impl: portal Start() returns the PipeWire node chosen by the user;
+        // a cancelled compositor dialog maps to PermissionDenied
+        self.node_id = Some(0);
+        let stub = CaptureSource { id: pattern.into(), ..source.clone() };
+        self.sink.start(&stub, config)
+    }
+
+    fn next_frame(&mut self) -> Option<CapturedFrame> {
+        // Real impl: pw_stream_dequeue_buffer, copy/convert BGRx, pw_stream_queue_buffer
+        self.sink.next_frame()
+    }
+
+    fn stop(&mut self) {
+        self.node_id = None;
+        self.sink.stop();
+    }
+
+    fn is_capturing(&self) -> bool { self.node_id.is_some() && self.sink.is_capturing() }
+}
+
+const V4L2_SYSFS: &str = "/sys/class/video4linux";
+const ASOUND_PCM: &str = "/proc/asound/pcm";
+
+/// V4L2 cameras and ALSA microphones
+#[derive(Debug, Default)]
+pub struct V4l2Devices;
+
+impl V4l2Devices {
+    pub fn new() -> Self { Self }
+
+    /// Either subsystem is present
+    pub fn is_available() -> bool {
+        Path::new(V4L2_SYSFS).exists() || Path::new(ASOUND_PCM).exists()
+    }
+
+    fn cameras() -> Vec<MediaDeviceInfo> {
+        let Ok(entries) = std::fs::read_dir(V4L2_SYSFS) else { return Vec::new() };
+        let mut cameras: Vec<MediaDeviceInfo> = entries.flatten()
+            .filter_map(|entry| {
+                let node = entry.file_name().to_string_lossy().into_owned();
+                // Index 0 is the capture node; higher indices are metadata nodes
+                let index = std::fs::read_to_string(entry.path().join("index")).unwrap_or_default();
+                if !node.starts_with("video") || index.trim() != "0" {
+                    return None;
+                }
+                let label = std::fs::read_to_string(entry.path().join("name")).unwrap_or_else(|_| node.clone());
+                Some(MediaDeviceInfo {
+                    device_id: format!("v4l2:/dev/{}", node),
+                    kind: MediaDeviceKind::VideoInput,
+                    label: label.trim().to_string(),
+                    group_id: format!("v4l2:{}", node),
+                })
+            })
+            .collect();
+        cameras.sort_by(|a, b| a.device_id.cmp(&b.device_id));
+        cameras
+    }
+}
+
+/// Capture PCMs from /proc/asound/pcm
+/// ("00-00: ALC3246 Analog : ALC3246 Analog : playback 1 : capture 1")
+fn parse_asound_pcm(contents: &str) -> Vec<MediaDeviceInfo> {
+    contents.lines()
+        .filter(|line| line.contains("capture"))
+        .filter_map(|line| {
+            let (ids, rest) = line.split_once(':')?;
+            let (card, device) = ids.trim().split_once('-')?;
+            let (card, device) = (card.parse::<u32>().ok()?, device.parse::<u32>().ok()?);
+            let label = rest.split(':').next()?.trim();
+            Some(MediaDeviceInfo {
+                device_id: format!("alsa:hw:{},{}", card, device),
+                kind: MediaDeviceKind::AudioInput,
+                label: label.to_string(),
+                group_id: format!("alsa:{}", card),
+            })
+        })
+        .collect()
+}
+
+impl CaptureDeviceBackend for V4l2Devices {
+    fn name(&self) -> &'static str { "v4l2" }
+
+    fn enumerate_devices(&self) -> Vec<MediaDeviceInfo> {
+        let mut devices = Self::cameras();
+        devices.extend(parse_asound_pcm(&std::fs::read_to_string(ASOUND_PCM).unwrap_or_default()));
+        devices
+    }
+
+    fn capabilities(&self, device_id: &str) -> Option<DeviceCapabilities> {
+        // Real impl: VIDIOC_ENUM_FMT / VIDIOC_ENUM_FRAMESIZES / VIDIOC_ENUM_FRAMEINTERVALS
+        // for cameras, snd_pcm_hw_params_test_rate/channels for microphones
+        if device_id.starts_with("v4l2:") {
+            Some(DeviceCapabilities { video_formats: common_camera_formats(), ..Default::default() })
+        } else if device_id.starts_with("alsa:") {
+            Some(DeviceCapabilities { audio_formats: common_microphone_formats(), ..Default::default() })
+        } else {
+            None
+        }
+    }
+
+    fn open(&self, device_id: &str) -> CaptureResult<Box<dyn CaptureDevice>> {
+        let info = self.enumerate_devices().into_iter().find(|d| d.device_id == device_id)
+            .ok_or_else(|| CaptureError::SourceNotFound(device_id.to_string()))?;
+        // Real impl: open the node (EBUSY maps to Backend, EACCES to PermissionDenied),
+        // VIDIOC_S_FMT (YUYV/MJPG), VIDIOC_S_PARM, VIDIOC_REQBUFS + mmap, VIDIOC_STREAMON;
+        // microphones: snd_pcm_open(SND_PCM_STREAM_CAPTURE) with FLOAT_LE interleaved
+        Ok(match info.kind {
+            MediaDeviceKind::VideoInput => Box::new(NullCamera::new(info)),
+            _ => Box::new(NullMicrophone::new(info)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_asound_pcm() {
+        let pcm = "00-00: ALC3246 Analog : ALC3246 Analog : playback 1 : capture 1\n\
+                   00-03: HDMI 0 : HDMI 0 : playback 1\n\
+                   01-00: USB Audio : USB Audio : capture 1\n";
+        let devices = parse_asound_pcm(pcm);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].device_id, "alsa:hw:0,0");
+        assert_eq!(devices[1].label, "USB Audio");
+        assert_eq!(devices[1].group_id, "alsa:1");
+    }
+}
@@ -0,0 +1,107 @@
+//! ScreenCaptureKit and AVFoundation Capture (macOS)
+//!
+//! This is a stub implementation. Full implementation would list
+//! SCShareableContent displays and windows, build an SCContentFilter for the
+//! chosen one and receive CMSampleBuffers from an SCStream
+//! (kCVPixelFormatType_32BGRA). Cameras and microphones would come from
+//! AVCaptureDevice discovery sessions and an AVCaptureSession.
+
+use super::device::{common_camera_formats, common_microphone_formats};
+use super::{
+    CaptureConfig, CaptureDevice, CaptureDeviceBackend, CaptureError, CaptureResult, CaptureSource,
+    CapturedFrame, DeviceCapabilities, FacingMode, MediaDeviceInfo, MediaDeviceKind, NullCamera,
+    NullCapture, NullMicrophone, ScreenCaptureBackend,
+};
+use crate::webrtc::screen::DisplaySurface;
+
+/// ScreenCaptureKit stream
+#[derive(Debug, Default)]
+pub struct ScreenCaptureKitCapture {
+    // In real impl: SCStream, SCStreamOutput delegate
+    sink: NullCapture,
+}
+
+impl ScreenCaptureKitCapture {
+    pub fn new() -> Self { Self::default() }
+}
+
+impl ScreenCaptureBackend for ScreenCaptureKitCapture {
+    fn name(&self) -> &'static str { "screencapturekit" }
+
+    fn enumerate_sources(&self) -> Vec<CaptureSource> {
+        // Real impl: SCShareableContent.getWithCompletionHandler (displays, windows)
+        vec![CaptureSource {
+            id: "display:main".into(), surface: DisplaySurface::Monitor, name: "Main Display".into(),
+            width: 1920, height: 1080, tab_id: None, has_audio: true,
+        }]
+    }
+
+    fn start(&mut self, source: &CaptureSource, config: CaptureConfig) -> CaptureResult<()> {
+        if source.id != "display:main" {
+            return Err(CaptureError::SourceNotFound(source.id.clone()));
+        }
+        // Real impl: SCStreamConfiguration (width, height, minimumFrameInterval,
+        // showsCursor), SCStream.startCapture; TCC denial maps to PermissionDenied
+        let stub = CaptureSource { id: "monitor:0".into(), ..source.clone() };
+        self.sink.start(&stub, config)
+    }
+
+    fn next_frame(&mut self) -> Option<CapturedFrame> { self.sink.next_frame() }
+    fn stop(&mut self) { self.sink.stop(); }
+    fn is_capturing(&self) -> bool { self.sink.is_capturing() }
+}
+
+/// AVFoundation cameras and microphones
+#[derive(Debug, Default)]
+pub struct AVFoundationDevices;
+
+impl AVFoundationDevices {
+    pub fn new() -> Self { Self }
+}
+
+impl CaptureDeviceBackend for AVFoundationDevices {
+    fn name(&self) -> &'static str { "avfoundation" }
+
+    fn enumerate_devices(&self) -> Vec<MediaDeviceInfo> {
+        // Real impl: AVCaptureDeviceDiscoverySession (BuiltInWideAngleCamera,
+        // External, Microphone); uniqueID is the device id, modelID the group
+        vec![
+            MediaDeviceInfo {
+                device_id: "avf:builtin-camera".into(), kind: MediaDeviceKind::VideoInput,
+                label: "FaceTime HD Camera".into(), group_id: "avf:builtin".into(),
+            },
+            MediaDeviceInfo {
+                device_id: "avf:builtin-microphone".into(), kind: MediaDeviceKind::AudioInput,
+                label: "Built-in Microphone".into(), group_id: "avf:builtin".into(),
+            },
+        ]
+    }
+
+    fn capabilities(&self, device_id: &str) -> Option<DeviceCapabilities> {
+        // Real impl: AVCaptureDevice.formats (dimensions, videoSupportedFrameRateRanges)
+        match device_id {
+            "avf:builtin-camera" => Some(DeviceCapabilities {
+                video_formats: common_camera_formats(),
+                facing_mode: Some(FacingMode::User),
+                ..Default::default()
+            }),
+            "avf:builtin-microphone" => Some(DeviceCapabilities {
+                audio_formats: common_microphone_formats(),
+                echo_cancellation: true,
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+
+    fn open(&self, device_id: &str) -> CaptureResult<Box<dyn CaptureDevice>> {
+        let info = self.enumerate_devices().into_iter().find(|d| d.device_id == device_id)
+            .ok_or_else(|| CaptureError::SourceNotFound(device_id.to_string()))?;
+        // Real impl: AVCaptureDeviceInput + AVCaptureVideoDataOutput (32BGRA) /
+        // AVCaptureAudioDataOutput; TCC denial maps to PermissionDenied
+        Ok(match info.kind {
+            MediaDeviceKind::VideoInput => Box::new(NullCamera::new(info)),
+            _ => Box::new(NullMicrophone::new(info)),
+        })
+    }
+}
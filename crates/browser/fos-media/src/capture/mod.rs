@@ -0,0 +1,408 @@
+//! Screen Capture
+//!
+//! Display capture backends behind one trait, used by `getDisplayMedia`.
+//! Each capture session owns its own backend instance. Cameras and
+//! microphones for `getUserMedia` live in [`device`].
+//!
+//! Backends:
+//! - Linux: PipeWire streams negotiated through the xdg-desktop-portal ScreenCast interface
+//! - macOS: ScreenCaptureKit (SCStream)
+//! - Windows: Windows.Graphics.Capture
+//! - Null: synthetic test pattern (tests, headless)
+//!
+//! Browser tabs are never captured through the OS: the browser pushes its
+//! own composited frames into a [`TabFrameSink`] that [`TabCapture`] reads.
+//! Canvases are captured the same way, through [`CanvasCaptureTrack`].
+
+mod canvas;
+mod device;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+pub use canvas::{CanvasCaptureError, CanvasCaptureTrack};
+pub use device::{
+    create_device_backend, AudioFormat, CaptureDevice, CaptureDeviceBackend, CaptureFormat,
+    DeviceCapabilities, FacingMode, MediaDeviceInfo, MediaDeviceKind, NullCamera, NullDevices,
+    NullMicrophone, VideoFormat,
+};
+#[cfg(target_os = "linux")]
+pub use linux::{PipeWireCapture, V4l2Devices};
+#[cfg(target_os = "macos")]
+pub use macos::{AVFoundationDevices, ScreenCaptureKitCapture};
+#[cfg(target_os = "windows")]
+pub use windows::{GraphicsCapture, MediaFoundationDevices};
+
+use crate::webrtc::screen::{CursorCaptureConstraint, DisplaySurface};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Something that can be captured: a monitor, a window or a browser tab
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureSource {
+    /// Backend-specific identifier (e.g. "monitor:0", "tab:3")
+    pub id: String,
+    pub surface: DisplaySurface,
+    /// Name shown in the source picker
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Browser tab id for `DisplaySurface::Browser` sources
+    pub tab_id: Option<u64>,
+    /// Source can also provide its audio (tab audio, system audio)
+    pub has_audio: bool,
+}
+
+impl CaptureSource {
+    /// Source for a browser tab
+    pub fn tab(tab_id: u64, title: &str, width: u32, height: u32) -> Self {
+        Self {
+            id: format!("tab:{}", tab_id),
+            surface: DisplaySurface::Browser,
+            name: title.to_string(),
+            width,
+            height,
+            tab_id: Some(tab_id),
+            has_audio: true,
+        }
+    }
+}
+
+/// Capture stream configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureConfig {
+    pub frame_rate: f64,
+    /// Frames are downscaled to fit within these bounds (0 = source size)
+    pub max_width: u32,
+    pub max_height: u32,
+    pub cursor: CursorCaptureConstraint,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self { frame_rate: 30.0, max_width: 0, max_height: 0, cursor: CursorCaptureConstraint::Always }
+    }
+}
+
+impl CaptureConfig {
+    /// Interval between delivered frames
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.frame_rate.max(1.0))
+    }
+
+    /// Output size for a source, keeping its aspect ratio
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let sx = if self.max_width > 0 { self.max_width as f64 / width.max(1) as f64 } else { 1.0 };
+        let sy = if self.max_height > 0 { self.max_height as f64 / height.max(1) as f64 } else { 1.0 };
+        let scale = sx.min(sy).min(1.0);
+        (((width as f64 * scale) as u32).max(1), ((height as f64 * scale) as u32).max(1))
+    }
+}
+
+/// One captured frame, BGRA 8-bit with a tight stride
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedFrame {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+    /// Time since the capture started
+    pub timestamp: Duration,
+}
+
+impl CapturedFrame {
+    /// Frame from a 0xAARRGGBB pixel buffer (the browser's surface format)
+    pub fn from_argb(pixels: &[u32], width: u32, height: u32, timestamp: Duration) -> Self {
+        let data = pixels.iter().take((width * height) as usize).flat_map(|p| p.to_le_bytes()).collect();
+        Self { width, height, data, timestamp }
+    }
+
+    /// Frame from RGBA bytes (canvas pixel data)
+    pub fn from_rgba(pixels: &[u8], width: u32, height: u32, timestamp: Duration) -> Self {
+        let data = pixels.chunks_exact(4)
+            .take((width * height) as usize)
+            .flat_map(|p| [p[2], p[1], p[0], p[3]])
+            .collect();
+        Self { width, height, data, timestamp }
+    }
+
+    /// Nearest-neighbour scale to `width` x `height`
+    pub fn scaled(&self, width: u32, height: u32) -> Self {
+        if width == self.width && height == self.height {
+            return self.clone();
+        }
+        let mut data = vec![0u8; (width * height * 4) as usize];
+        for y in 0..height {
+            let sy = (y as u64 * self.height as u64 / height.max(1) as u64) as usize;
+            for x in 0..width {
+                let sx = (x as u64 * self.width as u64 / width.max(1) as u64) as usize;
+                let src = (sy * self.width as usize + sx) * 4;
+                let dst = (y * width + x) as usize * 4;
+                if let Some(px) = self.data.get(src..src + 4) {
+                    data[dst..dst + 4].copy_from_slice(px);
+                }
+            }
+        }
+        Self { width, height, data, timestamp: self.timestamp }
+    }
+}
+
+/// Screen capture error
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum CaptureError {
+    #[error("Capture backend unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Capture source not found: {0}")]
+    SourceNotFound(String),
+
+    #[error("Capture denied by the system")]
+    PermissionDenied,
+
+    #[error("Not capturing")]
+    NotCapturing,
+
+    #[error("Capture error: {0}")]
+    Backend(String),
+}
+
+/// Result type for capture operations
+pub type CaptureResult<T> = Result<T, CaptureError>;
+
+/// Platform screen capture backend
+pub trait ScreenCaptureBackend: Send {
+    /// Backend name ("pipewire", "screencapturekit", "graphics-capture", "null", "tab")
+    fn name(&self) -> &'static str;
+
+    /// Monitors and windows that can be offered in the picker
+    fn enumerate_sources(&self) -> Vec<CaptureSource>;
+
+    /// Start capturing a source
+    fn start(&mut self, source: &CaptureSource, config: CaptureConfig) -> CaptureResult<()>;
+
+    /// Latest frame, if one is due since the previous call
+    fn next_frame(&mut self) -> Option<CapturedFrame>;
+
+    /// Stop capturing; the backend may be started again
+    fn stop(&mut self);
+
+    fn is_capturing(&self) -> bool;
+}
+
+/// Create the preferred backend for this platform
+pub fn create_capture_backend() -> Box<dyn ScreenCaptureBackend> {
+    #[cfg(target_os = "linux")]
+    {
+        if PipeWireCapture::is_available() {
+            return Box::new(PipeWireCapture::new());
+        }
+        Box::new(NullCapture::new())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Box::new(ScreenCaptureKitCapture::new())
+    }
+    #[cfg(target_os = "windows")]
+    {
+        Box::new(GraphicsCapture::new())
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        Box::new(NullCapture::new())
+    }
+}
+
+/// Paces frame delivery to the configured frame rate
+#[derive(Debug, Default)]
+struct FrameClock {
+    started: Option<Instant>,
+    last_frame: Option<Instant>,
+    interval: Duration,
+}
+
+impl FrameClock {
+    fn start(&mut self, config: &CaptureConfig) {
+        self.started = Some(Instant::now());
+        self.last_frame = None;
+        self.interval = config.frame_interval();
+    }
+
+    /// Timestamp of the next frame if one is due
+    fn tick(&mut self) -> Option<Duration> {
+        let started = self.started?;
+        let now = Instant::now();
+        if self.last_frame.is_some_and(|last| now.duration_since(last) < self.interval) {
+            return None;
+        }
+        self.last_frame = Some(now);
+        Some(now.duration_since(started))
+    }
+
+    fn stop(&mut self) { self.started = None; }
+}
+
+/// Backend producing a moving test pattern without touching the display
+#[derive(Debug, Default)]
+pub struct NullCapture {
+    source: Option<CaptureSource>,
+    size: (u32, u32),
+    clock: FrameClock,
+    frames: u64,
+}
+
+impl NullCapture {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn frames_delivered(&self) -> u64 { self.frames }
+}
+
+impl ScreenCaptureBackend for NullCapture {
+    fn name(&self) -> &'static str { "null" }
+
+    fn enumerate_sources(&self) -> Vec<CaptureSource> {
+        vec![
+            CaptureSource {
+                id: "monitor:0".into(), surface: DisplaySurface::Monitor, name: "Screen 1".into(),
+                width: 1280, height: 720, tab_id: None, has_audio: false,
+            },
+            CaptureSource {
+                id: "window:0".into(), surface: DisplaySurface::Window, name: "Test Window".into(),
+                width: 640, height: 480, tab_id: None, has_audio: false,
+            },
+        ]
+    }
+
+    fn start(&mut self, source: &CaptureSource, config: CaptureConfig) -> CaptureResult<()> {
+        if !self.enumerate_sources().iter().any(|s| s.id == source.id) {
+            return Err(CaptureError::SourceNotFound(source.id.clone()));
+        }
+        self.size = config.output_size(source.width, source.height);
+        self.source = Some(source.clone());
+        self.clock.start(&config);
+        Ok(())
+    }
+
+    fn next_frame(&mut self) -> Option<CapturedFrame> {
+        self.source.as_ref()?;
+        let timestamp = self.clock.tick()?;
+        let (width, height) = self.size;
+        // Vertical bar sweeping across a grey background
+        let bar = (self.frames * 8 % width as u64) as u32;
+        let mut data = Vec::with_capacity((width * height * 4) as usize);
+        for _ in 0..height {
+            for x in 0..width {
+                let v = if x.abs_diff(bar) < 8 { 0xFF } else { 0x40 };
+                data.extend_from_slice(&[v, v, v, 0xFF]);
+            }
+        }
+        self.frames += 1;
+        Some(CapturedFrame { width, height, data, timestamp })
+    }
+
+    fn stop(&mut self) {
+        self.source = None;
+        self.clock.stop();
+    }
+
+    fn is_capturing(&self) -> bool { self.source.is_some() }
+}
+
+/// Latest composited frame of a browser tab, shared between the browser
+/// (producer) and a [`TabCapture`] (consumer)
+#[derive(Debug, Clone, Default)]
+pub struct TabFrameSink(Arc<Mutex<Option<CapturedFrame>>>);
+
+impl TabFrameSink {
+    pub fn new() -> Self { Self::default() }
+
+    /// Replace the pending frame; unread frames are dropped
+    pub fn push(&self, frame: CapturedFrame) {
+        if let Ok(mut slot) = self.0.lock() {
+            *slot = Some(frame);
+        }
+    }
+
+    fn take(&self) -> Option<CapturedFrame> {
+        self.0.lock().ok().and_then(|mut slot| slot.take())
+    }
+}
+
+/// Captures a browser tab from frames pushed by the browser
+#[derive(Debug)]
+pub struct TabCapture {
+    sink: TabFrameSink,
+    config: Option<CaptureConfig>,
+    clock: FrameClock,
+}
+
+impl TabCapture {
+    pub fn new(sink: TabFrameSink) -> Self {
+        Self { sink, config: None, clock: FrameClock::default() }
+    }
+}
+
+impl ScreenCaptureBackend for TabCapture {
+    fn name(&self) -> &'static str { "tab" }
+
+    /// Tabs are listed by the browser, not the backend
+    fn enumerate_sources(&self) -> Vec<CaptureSource> { Vec::new() }
+
+    fn start(&mut self, source: &CaptureSource, config: CaptureConfig) -> CaptureResult<()> {
+        if source.tab_id.is_none() {
+            return Err(CaptureError::SourceNotFound(source.id.clone()));
+        }
+        self.config = Some(config);
+        self.clock.start(&config);
+        Ok(())
+    }
+
+    fn next_frame(&mut self) -> Option<CapturedFrame> {
+        let config = self.config?;
+        let frame = self.sink.take()?;
+        let timestamp = self.clock.tick()?;
+        let (width, height) = config.output_size(frame.width, frame.height);
+        Some(CapturedFrame { timestamp, ..frame.scaled(width, height) })
+    }
+
+    fn stop(&mut self) {
+        self.config = None;
+        self.clock.stop();
+    }
+
+    fn is_capturing(&self) -> bool { self.config.is_some() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_capture() {
+        let mut backend = NullCapture::new();
+        let source = backend.enumerate_sources()[0].clone();
+        let config = CaptureConfig { max_width: 640, ..CaptureConfig::default() };
+        backend.start(&source, config).unwrap();
+        let frame = backend.next_frame().unwrap();
+        assert_eq!((frame.width, frame.height), (640, 360));
+        assert_eq!(frame.data.len(), 640 * 360 * 4);
+        // Paced to the frame rate
+        assert!(backend.next_frame().is_none());
+        backend.stop();
+        assert!(!backend.is_capturing());
+    }
+
+    #[test]
+    fn test_tab_capture() {
+        let sink = TabFrameSink::new();
+        let mut backend = TabCapture::new(sink.clone());
+        backend.start(&CaptureSource::tab(3, "Tab", 4, 2), CaptureConfig { max_width: 2, ..CaptureConfig::default() }).unwrap();
+        assert!(backend.next_frame().is_none());
+        sink.push(CapturedFrame::from_argb(&[0xFF112233; 8], 4, 2, Duration::ZERO));
+        let frame = backend.next_frame().unwrap();
+        assert_eq!((frame.width, frame.height), (2, 1));
+        assert_eq!(&frame.data[..4], &[0x33, 0x22, 0x11, 0xFF]);
+    }
+}
@@ -0,0 +1,104 @@
+//! Windows.Graphics.Capture and Media Foundation
+//!
+//! This is a stub implementation. Full implementation would create a
+//! GraphicsCaptureItem for a monitor (CreateForMonitor) or window
+//! (CreateForWindow) and read Direct3D11CaptureFrames from a frame pool.
+//! Cameras and microphones would be Media Foundation sources read through
+//! IMFSourceReader.
+
+use super::device::{common_camera_formats, common_microphone_formats};
+use super::{
+    CaptureConfig, CaptureDevice, CaptureDeviceBackend, CaptureError, CaptureResult, CaptureSource,
+    CapturedFrame, DeviceCapabilities, MediaDeviceInfo, MediaDeviceKind, NullCamera, NullCapture,
+    NullMicrophone, ScreenCaptureBackend,
+};
+use crate::webrtc::screen::DisplaySurface;
+
+/// Windows.Graphics.Capture session
+#[derive(Debug, Default)]
+pub struct GraphicsCapture {
+    // In real impl: GraphicsCaptureItem, Direct3D11CaptureFramePool, GraphicsCaptureSession
+    sink: NullCapture,
+}
+
+impl GraphicsCapture {
+    pub fn new() -> Self { Self::default() }
+}
+
+impl ScreenCaptureBackend for GraphicsCapture {
+    fn name(&self) -> &'static str { "graphics-capture" }
+
+    fn enumerate_sources(&self) -> Vec<CaptureSource> {
+        // Real impl: EnumDisplayMonitors + EnumWindows (visible, non-cloaked)
+        vec![CaptureSource {
+            id: "monitor:primary".into(), surface: DisplaySurface::Monitor, name: "Display 1".into(),
+            width: 1920, height: 1080, tab_id: None, has_audio: true,
+        }]
+    }
+
+    fn start(&mut self, source: &CaptureSource, config: CaptureConfig) -> CaptureResult<()> {
+        if source.id != "monitor:primary" {
+            return Err(CaptureError::SourceNotFound(source.id.clone()));
+        }
+        // Real impl: Direct3D11CaptureFramePool::CreateFreeThreaded(B8G8R8A8UIntNormalized),
+        // session.IsCursorCaptureEnabled(config.cursor != Never), StartCapture
+        let stub = CaptureSource { id: "monitor:0".into(), ..source.clone() };
+        self.sink.start(&stub, config)
+    }
+
+    fn next_frame(&mut self) -> Option<CapturedFrame> { self.sink.next_frame() }
+    fn stop(&mut self) { self.sink.stop(); }
+    fn is_capturing(&self) -> bool { self.sink.is_capturing() }
+}
+
+/// Media Foundation cameras and microphones
+#[derive(Debug, Default)]
+pub struct MediaFoundationDevices;
+
+impl MediaFoundationDevices {
+    pub fn new() -> Self { Self }
+}
+
+impl CaptureDeviceBackend for MediaFoundationDevices {
+    fn name(&self) -> &'static str { "media-foundation" }
+
+    fn enumerate_devices(&self) -> Vec<MediaDeviceInfo> {
+        // Real impl: MFEnumDeviceSources (MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_GUID /
+        // AUDCAP_GUID); the symbolic link is the device id, the container id the group
+        vec![
+            MediaDeviceInfo {
+                device_id: "mf:camera:0".into(), kind: MediaDeviceKind::VideoInput,
+                label: "Integrated Camera".into(), group_id: "mf:0".into(),
+            },
+            MediaDeviceInfo {
+                device_id: "mf:microphone:0".into(), kind: MediaDeviceKind::AudioInput,
+                label: "Microphone Array".into(), group_id: "mf:0".into(),
+            },
+        ]
+    }
+
+    fn capabilities(&self, device_id: &str) -> Option<DeviceCapabilities> {
+        // Real impl: IMFSourceReader::GetNativeMediaType over all stream types
+        match device_id {
+            "mf:camera:0" => Some(DeviceCapabilities { video_formats: common_camera_formats(), ..Default::default() }),
+            "mf:microphone:0" => Some(DeviceCapabilities {
+                audio_formats: common_microphone_formats(),
+                echo_cancellation: true,
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+
+    fn open(&self, device_id: &str) -> CaptureResult<Box<dyn CaptureDevice>> {
+        let info = self.enumerate_devices().into_iter().find(|d| d.device_id == device_id)
+            .ok_or_else(|| CaptureError::SourceNotFound(device_id.to_string()))?;
+        // Real impl: MFCreateDeviceSource, MFCreateSourceReaderFromMediaSource with
+        // MFVideoFormat_RGB32 / MFAudioFormat_Float; E_ACCESSDENIED (privacy
+        // settings) maps to PermissionDenied
+        Ok(match info.kind {
+            MediaDeviceKind::VideoInput => Box::new(NullCamera::new(info)),
+            _ => Box::new(NullMicrophone::new(info)),
+        })
+    }
+}
@@ -3,6 +3,19 @@
 //! Codec support detection and media decoding/encoding.
 
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::capture::CapturedFrame;
+use crate::containers::{self, Packet, TrackInfo};
+use crate::decoders::hw::{capability_report, DecoderBackend, HwCodec};
+use crate::decoders::{self, AudioSamples, EncodedPacket, VideoFrame};
+use crate::pipeline::AudioDecoderBackend;
+
+/// Opus frame length: 20 ms
+const OPUS_FRAME_MS: u32 = 20;
+
+/// Opus encoder delay in 48 kHz samples, written to OpusHead
+const OPUS_PRE_SKIP: u16 = 312;
 
 /// Supported codec
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -47,6 +60,28 @@ impl CodecType {
     pub fn is_audio(&self) -> bool {
         !self.is_video()
     }
+    
+    fn from_hw_codec(codec: HwCodec) -> Self {
+        match codec {
+            HwCodec::H264 => Self::H264,
+            HwCodec::H265 => Self::H265,
+            HwCodec::Vp8 => Self::VP8,
+            HwCodec::Vp9 => Self::VP9,
+            HwCodec::Av1 => Self::AV1,
+        }
+    }
+    
+    /// Video decoder backend codec
+    fn hw_codec(&self) -> Option<HwCodec> {
+        Some(match self {
+            Self::H264 => HwCodec::H264,
+            Self::H265 => HwCodec::H265,
+            Self::VP8 => HwCodec::Vp8,
+            Self::VP9 => HwCodec::Vp9,
+            Self::AV1 => HwCodec::Av1,
+            _ => return None,
+        })
+    }
 }
 
 /// Codec configuration
@@ -61,6 +96,8 @@ pub struct CodecConfig {
     pub channels: Option<u32>,
     pub profile: Option<String>,
     pub level: Option<String>,
+    /// Out-of-band decoder setup (avcC, AudioSpecificConfig, OpusHead, ...)
+    pub description: Option<Vec<u8>>,
 }
 
 impl CodecConfig {
@@ -75,6 +112,7 @@ impl CodecConfig {
             channels: None,
             profile: None,
             level: None,
+            description: None,
         }
     }
     
@@ -89,8 +127,21 @@ impl CodecConfig {
             channels: Some(channels),
             profile: None,
             level: None,
+            description: None,
         }
     }
+    
+    /// Decoder configuration for a demuxed track
+    pub fn from_track(track: &TrackInfo) -> Option<Self> {
+        let codec = containers::codec_type(track.codec)?;
+        let mut config = if codec.is_video() {
+            Self { frame_rate: track.frame_rate, ..Self::video(codec, track.width?, track.height?) }
+        } else {
+            Self::audio(codec, track.sample_rate?, track.channels?)
+        };
+        config.description = track.description();
+        Some(config)
+    }
 }
 
 /// Codec support check result
@@ -102,7 +153,7 @@ pub enum CodecSupport {
 }
 
 /// Codec registry
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct CodecRegistry {
     /// Registered decoders
     decoders: HashMap<CodecType, CodecInfo>,
@@ -128,19 +179,40 @@ impl CodecRegistry {
     }
     
     fn register_defaults(&mut self) {
-        // Register common decoders
-        let video_codecs = [CodecType::H264, CodecType::VP8, CodecType::VP9];
-        for codec in video_codecs {
-            self.decoders.insert(codec, CodecInfo {
+        // Register common decoders. The software VP8 and VP9 decoders
+        // cannot decode pictures yet, so those are only playable with a
+        // platform decoder
+        self.decoders.insert(CodecType::H264, CodecInfo {
+            codec: CodecType::H264,
+            hardware_accelerated: false,
+            max_width: 4096,
+            max_height: 2160,
+            max_frame_rate: 60.0,
+        });
+        self.decoders.insert(CodecType::AV1, CodecInfo {
+            codec: CodecType::AV1,
+            hardware_accelerated: false,
+            max_width: 8192,
+            max_height: 4320,
+            max_frame_rate: 60.0,
+        });
+        
+        // Platform decoders raise the limits, and add codecs with no software path
+        for cap in capability_report() {
+            let codec = CodecType::from_hw_codec(cap.codec);
+            let info = self.decoders.entry(codec).or_insert(CodecInfo {
                 codec,
-                hardware_accelerated: false,
-                max_width: 4096,
-                max_height: 2160,
+                hardware_accelerated: true,
+                max_width: 0,
+                max_height: 0,
                 max_frame_rate: 60.0,
             });
+            info.hardware_accelerated = true;
+            info.max_width = info.max_width.max(cap.max_width);
+            info.max_height = info.max_height.max(cap.max_height);
         }
         
-        let audio_codecs = [CodecType::AAC, CodecType::MP3, CodecType::Opus, CodecType::Vorbis];
+        let audio_codecs = [CodecType::AAC, CodecType::MP3, CodecType::Opus, CodecType::Vorbis, CodecType::FLAC];
         for codec in audio_codecs {
             self.decoders.insert(codec, CodecInfo {
                 codec,
@@ -150,6 +222,22 @@ impl CodecRegistry {
                 max_frame_rate: 0.0,
             });
         }
+        
+        // Encoders for MediaRecorder
+        self.encoders.insert(CodecType::VP8, CodecInfo {
+            codec: CodecType::VP8,
+            hardware_accelerated: false,
+            max_width: 4096,
+            max_height: 2160,
+            max_frame_rate: 60.0,
+        });
+        self.encoders.insert(CodecType::Opus, CodecInfo {
+            codec: CodecType::Opus,
+            hardware_accelerated: false,
+            max_width: 0,
+            max_height: 0,
+            max_frame_rate: 0.0,
+        });
     }
     
     /// Check if codec is supported for decoding
@@ -184,6 +272,16 @@ impl CodecRegistry {
         }
     }
     
+    /// Limits of a registered decoder
+    pub fn decoder_info(&self, codec: CodecType) -> Option<&CodecInfo> {
+        self.decoders.get(&codec)
+    }
+    
+    /// Limits of a registered encoder
+    pub fn encoder_info(&self, codec: CodecType) -> Option<&CodecInfo> {
+        self.encoders.get(&codec)
+    }
+    
     /// Register a decoder
     pub fn register_decoder(&mut self, info: CodecInfo) {
         self.decoders.insert(info.codec, info);
@@ -193,15 +291,71 @@ impl CodecRegistry {
     pub fn register_encoder(&mut self, info: CodecInfo) {
         self.encoders.insert(info.codec, info);
     }
+    
+    /// Configured video encoder for a registered codec
+    pub fn create_video_encoder(&self, config: CodecConfig) -> Result<VideoEncoder, CodecError> {
+        let info = self.encoders.get(&config.codec).filter(|i| i.codec.is_video()).ok_or(CodecError::Unsupported)?;
+        if config.width.unwrap_or(0) > info.max_width || config.height.unwrap_or(0) > info.max_height {
+            return Err(CodecError::Unsupported);
+        }
+        let mut encoder = VideoEncoder::new(config);
+        encoder.configure()?;
+        Ok(encoder)
+    }
+    
+    /// Configured audio encoder for a registered codec
+    pub fn create_audio_encoder(&self, config: CodecConfig) -> Result<AudioEncoder, CodecError> {
+        if !self.encoders.get(&config.codec).is_some_and(|i| i.codec.is_audio()) {
+            return Err(CodecError::Unsupported);
+        }
+        let mut encoder = AudioEncoder::new(config);
+        encoder.configure()?;
+        Ok(encoder)
+    }
 }
 
 /// Video decoder
-#[derive(Debug)]
 pub struct VideoDecoder {
     pub config: CodecConfig,
     pub state: DecoderState,
+    decoder: Option<DecoderBackend>,
     /// Decoded frames queue
     frames: Vec<DecodedFrame>,
+    metrics: DecodeMetrics,
+}
+
+impl std::fmt::Debug for VideoDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VideoDecoder").field("config", &self.config).field("state", &self.state).finish()
+    }
+}
+
+/// Decode latency of the frames a video decoder produced: the time from
+/// submitting a chunk until its frames came out
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DecodeMetrics {
+    pub frames: u64,
+    pub last_latency: Duration,
+    pub max_latency: Duration,
+    pub total_latency: Duration,
+}
+
+impl DecodeMetrics {
+    fn record(&mut self, latency: Duration) {
+        self.frames += 1;
+        self.last_latency = latency;
+        self.max_latency = self.max_latency.max(latency);
+        self.total_latency += latency;
+    }
+    
+    /// Mean latency per frame
+    pub fn average_latency(&self) -> Duration {
+        if self.frames == 0 {
+            Duration::ZERO
+        } else {
+            self.total_latency.div_f64(self.frames as f64)
+        }
+    }
 }
 
 /// Decoder state
@@ -228,50 +382,109 @@ pub struct DecodedFrame {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PixelFormat {
     I420,
+    /// 4:2:0 with 10-bit samples in little-endian 16-bit words
+    I420P10,
+    I422,
+    I444,
     NV12,
     RGBA,
     BGRA,
 }
 
+/// Decoder output packed plane after plane, without row padding
+impl From<VideoFrame> for DecodedFrame {
+    fn from(frame: VideoFrame) -> Self {
+        let (w, h) = (frame.width as usize, frame.height as usize);
+        let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+        // Bytes per row and rows of each plane
+        let (format, layout): (_, &[(usize, usize)]) = match frame.format {
+            decoders::PixelFormat::I420 => (PixelFormat::I420, &[(w, h), (cw, ch), (cw, ch)]),
+            decoders::PixelFormat::I420_10 => (PixelFormat::I420P10, &[(w * 2, h), (cw * 2, ch), (cw * 2, ch)]),
+            decoders::PixelFormat::I422 => (PixelFormat::I422, &[(w, h), (cw, h), (cw, h)]),
+            decoders::PixelFormat::I444 => (PixelFormat::I444, &[(w, h), (w, h), (w, h)]),
+            decoders::PixelFormat::Nv12 => (PixelFormat::NV12, &[(w, h), (cw * 2, ch)]),
+            decoders::PixelFormat::Rgba => (PixelFormat::RGBA, &[(w * 4, h)]),
+            decoders::PixelFormat::Bgra => (PixelFormat::BGRA, &[(w * 4, h)]),
+        };
+        let mut data = Vec::with_capacity(layout.iter().map(|(row, rows)| row * rows).sum());
+        for (plane, &(row_bytes, rows)) in frame.planes.iter().zip(layout) {
+            for y in 0..rows {
+                let start = data.len();
+                data.extend(plane.data.iter().skip(y * plane.stride).take(row_bytes));
+                data.resize(start + row_bytes, 0);
+            }
+        }
+        Self {
+            timestamp: frame.pts.as_secs_f64(),
+            duration: frame.duration.as_secs_f64(),
+            width: frame.width,
+            height: frame.height,
+            data,
+            format,
+        }
+    }
+}
+
 impl VideoDecoder {
     pub fn new(config: CodecConfig) -> Self {
         Self {
             config,
             state: DecoderState::Unconfigured,
+            decoder: None,
             frames: Vec::new(),
+            metrics: DecodeMetrics::default(),
         }
     }
     
+    /// Set up the decoder, with the `description` (avcC, hvcC, vpcC, av1C)
+    /// when the container has one
     pub fn configure(&mut self) -> Result<(), CodecError> {
+        let codec = self.config.codec.hw_codec().ok_or(CodecError::Unsupported)?;
+        let mut backend = DecoderBackend::new(codec);
+        if let Some(description) = &self.config.description {
+            backend.configure(description).map_err(|e| CodecError::DecodeError(e.to_string()))?;
+        }
+        self.decoder = Some(backend);
+        self.frames.clear();
         self.state = DecoderState::Configured;
         Ok(())
     }
     
-    pub fn decode(&mut self, _chunk: &EncodedChunk) -> Result<(), CodecError> {
-        if self.state != DecoderState::Configured {
+    pub fn decode(&mut self, chunk: &EncodedChunk) -> Result<(), CodecError> {
+        let (DecoderState::Configured, Some(backend)) = (self.state, self.decoder.as_mut()) else {
             return Err(CodecError::InvalidState);
-        }
-        
-        // Placeholder - actual decoding would use FFmpeg/GStreamer
-        let frame = DecodedFrame {
-            timestamp: 0.0,
-            duration: 1.0 / 30.0,
-            width: self.config.width.unwrap_or(1920),
-            height: self.config.height.unwrap_or(1080),
-            data: Vec::new(),
-            format: PixelFormat::I420,
         };
-        self.frames.push(frame);
-        
+        let pts = Duration::from_secs_f64(chunk.timestamp.max(0.0));
+        let packet = EncodedPacket { data: chunk.data.clone(), pts, dts: pts, is_key: chunk.is_key };
+        let start = Instant::now();
+        let frames = backend.decode(&packet).map_err(|e| CodecError::DecodeError(e.to_string()))?;
+        let latency = start.elapsed();
+        for frame in frames {
+            self.metrics.record(latency);
+            let mut frame = DecodedFrame::from(frame);
+            if let Some(duration) = chunk.duration {
+                frame.duration = duration;
+            }
+            self.frames.push(frame);
+        }
         Ok(())
     }
     
+    /// Decode latency of the frames so far
+    pub fn metrics(&self) -> DecodeMetrics {
+        self.metrics
+    }
+    
     pub fn flush(&mut self) -> Vec<DecodedFrame> {
+        if let Some(backend) = self.decoder.as_mut() {
+            self.frames.extend(backend.flush().into_iter().map(DecodedFrame::from));
+        }
         std::mem::take(&mut self.frames)
     }
     
     pub fn close(&mut self) {
         self.state = DecoderState::Closed;
+        self.decoder = None;
         self.frames.clear();
     }
 }
@@ -285,11 +498,24 @@ pub struct EncodedChunk {
     pub is_key: bool,
 }
 
+/// Demuxed packets are fed to decoders as chunks timed in seconds
+impl From<&Packet> for EncodedChunk {
+    fn from(packet: &Packet) -> Self {
+        Self {
+            data: packet.data.clone(),
+            timestamp: packet.pts.as_secs_f64(),
+            duration: (!packet.duration.is_zero()).then(|| packet.duration.as_secs_f64()),
+            is_key: packet.is_key,
+        }
+    }
+}
+
 /// Audio decoder
 #[derive(Debug)]
 pub struct AudioDecoder {
     pub config: CodecConfig,
     pub state: DecoderState,
+    decoder: Option<AudioDecoderBackend>,
     samples: Vec<DecodedAudio>,
 }
 
@@ -303,36 +529,255 @@ pub struct DecodedAudio {
     pub data: Vec<f32>,
 }
 
+impl From<AudioSamples> for DecodedAudio {
+    fn from(samples: AudioSamples) -> Self {
+        Self {
+            timestamp: samples.pts.as_secs_f64(),
+            duration: samples.duration.as_secs_f64(),
+            sample_rate: samples.sample_rate,
+            channels: samples.channels,
+            data: samples.data,
+        }
+    }
+}
+
 impl AudioDecoder {
     pub fn new(config: CodecConfig) -> Self {
         Self {
             config,
             state: DecoderState::Unconfigured,
+            decoder: None,
             samples: Vec::new(),
         }
     }
     
+    /// Set up the decoder, with the `description` (OpusHead, Vorbis
+    /// headers, FLAC STREAMINFO) when the codec needs one
     pub fn configure(&mut self) -> Result<(), CodecError> {
+        let mut backend = AudioDecoderBackend::for_codec(self.config.codec).ok_or(CodecError::Unsupported)?;
+        if let Some(description) = &self.config.description {
+            backend.decoder().configure(description).map_err(|e| CodecError::DecodeError(e.to_string()))?;
+        }
+        self.decoder = Some(backend);
+        self.samples.clear();
         self.state = DecoderState::Configured;
         Ok(())
     }
     
-    pub fn decode(&mut self, _chunk: &EncodedChunk) -> Result<(), CodecError> {
-        if self.state != DecoderState::Configured {
+    pub fn decode(&mut self, chunk: &EncodedChunk) -> Result<(), CodecError> {
+        let (DecoderState::Configured, Some(backend)) = (self.state, self.decoder.as_mut()) else {
             return Err(CodecError::InvalidState);
+        };
+        let pts = std::time::Duration::from_secs_f64(chunk.timestamp.max(0.0));
+        let packet = EncodedPacket { data: chunk.data.clone(), pts, dts: pts, is_key: chunk.is_key };
+        let samples = backend.decoder().decode(&packet).map_err(|e| CodecError::DecodeError(e.to_string()))?;
+        // In-band headers produce no audio
+        if !samples.data.is_empty() {
+            self.samples.push(samples.into());
         }
         Ok(())
     }
     
+    /// Decoded audio so far; the next chunk must be a key chunk again
     pub fn flush(&mut self) -> Vec<DecodedAudio> {
+        if let Some(backend) = self.decoder.as_mut() {
+            let decoder = backend.decoder();
+            if let Some(samples) = decoder.flush() {
+                if !samples.data.is_empty() {
+                    self.samples.push(samples.into());
+                }
+            }
+            decoder.reset();
+        }
         std::mem::take(&mut self.samples)
     }
     
+    pub fn close(&mut self) {
+        self.state = DecoderState::Closed;
+        self.decoder = None;
+        self.samples.clear();
+    }
+}
+
+/// Video encoder
+#[derive(Debug)]
+pub struct VideoEncoder {
+    pub config: CodecConfig,
+    pub state: DecoderState,
+    /// Frames from one key frame to the next
+    pub key_frame_interval: u32,
+    frames_since_key: u32,
+    /// Size of the last frame; a new size needs a key frame
+    size: Option<(u32, u32)>,
+}
+
+impl VideoEncoder {
+    pub fn new(config: CodecConfig) -> Self {
+        // A key frame every two seconds
+        let key_frame_interval = (config.frame_rate.unwrap_or(30.0) * 2.0).round().max(1.0) as u32;
+        Self {
+            config,
+            state: DecoderState::Unconfigured,
+            key_frame_interval,
+            frames_since_key: 0,
+            size: None,
+        }
+    }
+    
+    pub fn configure(&mut self) -> Result<(), CodecError> {
+        if self.config.codec != CodecType::VP8 {
+            return Err(CodecError::Unsupported);
+        }
+        self.state = DecoderState::Configured;
+        Ok(())
+    }
+    
+    /// Encode a frame at `timestamp` seconds
+    pub fn encode(&mut self, frame: &CapturedFrame, timestamp: f64, force_key: bool) -> Result<EncodedChunk, CodecError> {
+        if self.state != DecoderState::Configured {
+            return Err(CodecError::InvalidState);
+        }
+        if frame.width == 0 || frame.height == 0 || frame.width > 0x3FFF || frame.height > 0x3FFF {
+            return Err(CodecError::EncodeError(format!("invalid frame size {}x{}", frame.width, frame.height)));
+        }
+        
+        let size = (frame.width, frame.height);
+        let is_key = force_key || self.size != Some(size) || self.frames_since_key >= self.key_frame_interval;
+        self.frames_since_key = if is_key { 1 } else { self.frames_since_key + 1 };
+        self.size = Some(size);
+        
+        // Placeholder - actual compression would use libvpx. The frame
+        // header is real: frame tag, then start code and size on key frames
+        let first_partition_size = 0u32;
+        let tag = u32::from(!is_key) | (1 << 4) | (first_partition_size << 5);
+        let mut data = tag.to_le_bytes()[..3].to_vec();
+        if is_key {
+            data.extend_from_slice(&[0x9D, 0x01, 0x2A]);
+            data.extend_from_slice(&(frame.width as u16).to_le_bytes());
+            data.extend_from_slice(&(frame.height as u16).to_le_bytes());
+        }
+        
+        Ok(EncodedChunk {
+            data,
+            timestamp,
+            duration: self.config.frame_rate.map(|r| 1.0 / r),
+            is_key,
+        })
+    }
+    
     pub fn close(&mut self) {
         self.state = DecoderState::Closed;
     }
 }
 
+/// Audio encoder
+#[derive(Debug)]
+pub struct AudioEncoder {
+    pub config: CodecConfig,
+    pub state: DecoderState,
+    /// Interleaved samples not yet making up a whole frame
+    pending: Vec<f32>,
+    /// Timestamp of the first pending sample, in seconds
+    pending_timestamp: f64,
+}
+
+impl AudioEncoder {
+    pub fn new(config: CodecConfig) -> Self {
+        Self {
+            config,
+            state: DecoderState::Unconfigured,
+            pending: Vec::new(),
+            pending_timestamp: 0.0,
+        }
+    }
+    
+    pub fn configure(&mut self) -> Result<(), CodecError> {
+        let rate_supported = matches!(self.config.sample_rate, Some(8000 | 12000 | 16000 | 24000 | 48000));
+        let channels_supported = matches!(self.config.channels, Some(1 | 2));
+        if self.config.codec != CodecType::Opus || !rate_supported || !channels_supported {
+            return Err(CodecError::Unsupported);
+        }
+        self.state = DecoderState::Configured;
+        Ok(())
+    }
+    
+    fn sample_rate(&self) -> u32 {
+        self.config.sample_rate.unwrap_or(48000)
+    }
+    
+    fn channels(&self) -> u32 {
+        self.config.channels.unwrap_or(2)
+    }
+    
+    /// Samples per channel in one frame
+    pub fn frame_size(&self) -> usize {
+        (self.sample_rate() * OPUS_FRAME_MS / 1000) as usize
+    }
+    
+    /// `OpusHead` identification header, the WebM CodecPrivate
+    pub fn codec_private(&self) -> Vec<u8> {
+        let mut head = b"OpusHead".to_vec();
+        head.push(1); // version
+        head.push(self.channels() as u8);
+        head.extend_from_slice(&OPUS_PRE_SKIP.to_le_bytes());
+        head.extend_from_slice(&self.sample_rate().to_le_bytes());
+        head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+        head.push(0); // channel mapping family
+        head
+    }
+    
+    /// Encode interleaved samples starting at `timestamp` seconds; returns
+    /// the packets of every completed 20 ms frame
+    pub fn encode(&mut self, samples: &[f32], timestamp: f64) -> Result<Vec<EncodedChunk>, CodecError> {
+        if self.state != DecoderState::Configured {
+            return Err(CodecError::InvalidState);
+        }
+        if self.pending.is_empty() {
+            self.pending_timestamp = timestamp;
+        }
+        self.pending.extend_from_slice(samples);
+        
+        let frame_len = self.frame_size() * self.channels() as usize;
+        let duration = OPUS_FRAME_MS as f64 / 1000.0;
+        let mut chunks = Vec::new();
+        while self.pending.len() >= frame_len {
+            self.pending.drain(..frame_len);
+            chunks.push(self.packet(duration));
+        }
+        Ok(chunks)
+    }
+    
+    /// Pad and encode the remaining samples
+    pub fn flush(&mut self) -> Vec<EncodedChunk> {
+        if self.state != DecoderState::Configured || self.pending.is_empty() {
+            return Vec::new();
+        }
+        self.pending.clear();
+        vec![self.packet(OPUS_FRAME_MS as f64 / 1000.0)]
+    }
+    
+    fn packet(&mut self, duration: f64) -> EncodedChunk {
+        // Placeholder - actual compression would use libopus. The TOC byte
+        // is real: CELT fullband 20 ms, one frame; an empty frame decodes
+        // as silence
+        let stereo = u8::from(self.channels() == 2);
+        let toc = (31 << 3) | (stereo << 2);
+        let chunk = EncodedChunk {
+            data: vec![toc],
+            timestamp: self.pending_timestamp,
+            duration: Some(duration),
+            is_key: true,
+        };
+        self.pending_timestamp += duration;
+        chunk
+    }
+    
+    pub fn close(&mut self) {
+        self.state = DecoderState::Closed;
+        self.pending.clear();
+    }
+}
+
 /// Codec error
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum CodecError {
@@ -358,7 +803,11 @@ mod tests {
         let registry = CodecRegistry::new();
         
         assert_eq!(registry.is_decode_supported(CodecType::H264), CodecSupport::Supported);
-        assert_eq!(registry.is_decode_supported(CodecType::AV1), CodecSupport::Unsupported);
+        assert_eq!(registry.is_decode_supported(CodecType::AV1), CodecSupport::Supported);
+        assert_eq!(registry.is_decode_supported(CodecType::H265), CodecSupport::Unsupported);
+        assert_eq!(registry.is_decode_supported(CodecType::VP8), CodecSupport::Unsupported);
+        assert_eq!(registry.is_decode_supported(CodecType::VP9), CodecSupport::Unsupported);
+        assert_eq!(registry.is_config_supported(&CodecConfig::video(CodecType::AV1, 7680, 4320)), CodecSupport::Supported);
     }
     
     #[test]
@@ -370,6 +819,94 @@ mod tests {
         assert_eq!(decoder.state, DecoderState::Configured);
     }
     
+    #[test]
+    fn test_av1_decode_metrics() {
+        use crate::decoders::video::av1::TEST_STREAM;
+        
+        let mut decoder = VideoDecoder::new(CodecConfig::video(CodecType::AV1, 16, 16));
+        decoder.configure().unwrap();
+        for (i, data) in TEST_STREAM.iter().enumerate() {
+            let chunk = EncodedChunk { data: data.to_vec(), timestamp: i as f64 * 0.04, duration: Some(0.04), is_key: i == 0 };
+            decoder.decode(&chunk).unwrap();
+        }
+        let frames = decoder.flush();
+        assert_eq!(frames.len(), 2);
+        assert_eq!((frames[1].timestamp, frames[1].duration, frames[1].format), (0.04, 0.04, PixelFormat::I420));
+        assert_eq!(frames[0].data.len(), 16 * 16 * 3 / 2);
+        
+        let metrics = decoder.metrics();
+        assert_eq!(metrics.frames, 2);
+        assert!(metrics.max_latency >= metrics.average_latency() && metrics.max_latency >= metrics.last_latency);
+        assert!(metrics.max_latency > Duration::ZERO);
+    }
+    
+    #[test]
+    fn test_encoders() {
+        let registry = CodecRegistry::new();
+        assert_eq!(registry.is_encode_supported(CodecType::VP8), CodecSupport::Supported);
+        assert!(registry.create_video_encoder(CodecConfig::video(CodecType::VP9, 64, 64)).is_err());
+        
+        let mut video = registry.create_video_encoder(CodecConfig::video(CodecType::VP8, 0, 0)).unwrap();
+        let frame = CapturedFrame { width: 320, height: 240, data: vec![0; 320 * 240 * 4], timestamp: std::time::Duration::ZERO };
+        let key = video.encode(&frame, 0.0, false).unwrap();
+        assert!(key.is_key);
+        assert_eq!(&key.data[3..], &[0x9D, 0x01, 0x2A, 0x40, 0x01, 0xF0, 0x00]);
+        assert!(!video.encode(&frame, 0.033, false).unwrap().is_key);
+        
+        let mut audio = registry.create_audio_encoder(CodecConfig::audio(CodecType::Opus, 48000, 2)).unwrap();
+        assert!(registry.create_audio_encoder(CodecConfig::audio(CodecType::Opus, 44100, 2)).is_err());
+        assert!(audio.encode(&[0.0; 1000], 1.0).unwrap().is_empty());
+        let packets = audio.encode(&[0.0; 1000], 1.0).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert_eq!((packets[0].timestamp, packets[0].data[0]), (1.0, 0xFC));
+        assert!((audio.flush()[0].timestamp - 1.02).abs() < 1e-9);
+        assert_eq!(&audio.codec_private()[..9], b"OpusHead\x01");
+    }
+    
+    #[test]
+    fn test_audio_decoder() {
+        let registry = CodecRegistry::new();
+        let mut encoder = registry.create_audio_encoder(CodecConfig::audio(CodecType::Opus, 48000, 2)).unwrap();
+        let packets = encoder.encode(&[0.0; 960 * 2], 0.5).unwrap();
+        
+        let config = CodecConfig { description: Some(encoder.codec_private()), ..CodecConfig::audio(CodecType::Opus, 48000, 2) };
+        let mut decoder = AudioDecoder::new(config);
+        assert!(matches!(decoder.decode(&packets[0]), Err(CodecError::InvalidState)));
+        decoder.configure().unwrap();
+        for packet in &packets {
+            decoder.decode(packet).unwrap();
+        }
+        let decoded = decoder.flush();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!((decoded[0].timestamp, decoded[0].sample_rate, decoded[0].channels), (0.5, 48000, 2));
+        // The pre-skip is dropped from the first packet
+        assert_eq!(decoded[0].data.len(), (960 - OPUS_PRE_SKIP as usize) * 2);
+        assert!(decoded[0].data.iter().all(|&s| s == 0.0));
+        
+        let mut mp3 = AudioDecoder::new(CodecConfig::audio(CodecType::MP3, 44100, 2));
+        assert!(matches!(mp3.configure(), Err(CodecError::Unsupported)));
+    }
+    
+    #[test]
+    fn test_demuxed_track_ingestion() {
+        use fos_demux::fixtures::{init_segment, media_segment};
+        use crate::containers::{fmp4::Fmp4Demuxer, Demuxer};
+        
+        let mut demuxer = Fmp4Demuxer::new();
+        let packets = demuxer.append(&[init_segment(&[], true), media_segment(0, &[3, 2])].concat()).unwrap();
+        let track = demuxer.video_track().unwrap();
+        let config = CodecConfig::from_track(track).unwrap();
+        assert_eq!((config.codec, config.width, config.height), (CodecType::H264, Some(320), Some(240)));
+        assert_eq!(config.description.as_deref(), Some(track.codec_private.as_slice()));
+        assert_eq!(track.codec_string().as_deref(), Some("avc1.42001E"));
+        
+        let chunk = EncodedChunk::from(&packets[1]);
+        assert_eq!((chunk.timestamp, chunk.duration, chunk.is_key), (0.1, Some(0.1), false));
+        let mut decoder = VideoDecoder::new(config);
+        decoder.configure().unwrap();
+        decoder.decode(&EncodedChunk::from(&packets[0])).unwrap();
+    }
+    
     #[test]
     fn test_codec_types() {
         assert!(CodecType::H264.is_video());
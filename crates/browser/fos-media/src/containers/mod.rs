@@ -1,149 +1,57 @@
 //! Container Parsers (Demuxers)
 //!
-//! Parsers for media container formats: MP4, WebM, MKV, MPEG-TS, fMP4.
-
-pub mod mp4;
-pub mod webm;
-pub mod mkv;
-pub mod ts;
-pub mod fmp4;
-
-use std::time::Duration;
-use crate::decoders::EncodedPacket;
-
-/// Demuxer trait
-pub trait Demuxer: Send {
-    /// Get duration of media
-    fn duration(&self) -> Option<Duration>;
-    
-    /// Get video track info
-    fn video_track(&self) -> Option<&TrackInfo>;
-    
-    /// Get audio track info
-    fn audio_track(&self) -> Option<&TrackInfo>;
-    
-    /// Read next packet
-    fn read_packet(&mut self) -> DemuxerResult<Packet>;
-    
-    /// Seek to position
-    fn seek(&mut self, position: Duration) -> DemuxerResult<()>;
-    
-    /// Check if at end of stream
-    fn is_eof(&self) -> bool;
-}
-
-/// Track information
-#[derive(Debug, Clone)]
-pub struct TrackInfo {
-    pub track_id: u32,
-    pub track_type: TrackType,
-    pub codec: CodecId,
-    pub duration: Duration,
-    pub timescale: u32,
-    /// For video
-    pub width: Option<u32>,
-    pub height: Option<u32>,
-    pub frame_rate: Option<f64>,
-    /// For audio  
-    pub sample_rate: Option<u32>,
-    pub channels: Option<u32>,
-    /// Codec-specific data (SPS/PPS, AudioSpecificConfig, etc.)
-    pub codec_private: Vec<u8>,
-}
-
-/// Track type
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TrackType { Video, Audio, Subtitle, Data }
-
-/// Codec identifier
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum CodecId {
-    H264, H265, Vp8, Vp9, Av1,
-    Aac, Mp3, Opus, Vorbis, Flac, Pcm,
-    WebVtt, Subrip,
-    Unknown,
-}
-
-/// Demuxed packet
-#[derive(Debug)]
-pub struct Packet {
-    pub track_id: u32,
-    pub pts: Duration,
-    pub dts: Duration,
-    pub duration: Duration,
-    pub is_key: bool,
-    pub data: Vec<u8>,
-}
-
-impl Packet {
-    pub fn to_encoded_packet(&self) -> EncodedPacket {
-        EncodedPacket { data: self.data.clone(), pts: self.pts, dts: self.dts, is_key: self.is_key }
-    }
-}
-
-/// Demuxer result type
-pub type DemuxerResult<T> = Result<T, DemuxerError>;
-
-/// Demuxer error
-#[derive(Debug, Clone, thiserror::Error)]
-pub enum DemuxerError {
-    #[error("Invalid container: {0}")]
-    InvalidContainer(String),
-    #[error("Unsupported feature: {0}")]
-    Unsupported(String),
-    #[error("Need more data")]
-    NeedMoreData,
-    #[error("End of stream")]
-    EndOfStream,
-    #[error("IO error: {0}")]
-    IoError(String),
-}
-
-/// Container format detection
-pub fn detect_format(data: &[u8]) -> Option<ContainerFormat> {
-    if data.len() < 12 { return None; }
-    
-    // MP4/MOV: ftyp box or moov box
-    if &data[4..8] == b"ftyp" || &data[4..8] == b"moov" || &data[4..8] == b"mdat" {
-        return Some(ContainerFormat::Mp4);
+//! The parsers live in the `fos-demux` crate, shared by the media element,
+//! MSE and WebCodecs; this module re-exports them and answers `canPlayType`
+//! against the codec registry.
+
+pub use fos_demux::*;
+
+use crate::codecs::{CodecRegistry, CodecSupport, CodecType};
+
+/// Registry codec for a container codec id
+pub(crate) fn codec_type(codec: CodecId) -> Option<CodecType> {
+    Some(match codec {
+        CodecId::H264 => CodecType::H264, CodecId::H265 => CodecType::H265, CodecId::Vp8 => CodecType::VP8,
+        CodecId::Vp9 => CodecType::VP9, CodecId::Av1 => CodecType::AV1, CodecId::Aac => CodecType::AAC,
+        CodecId::Mp3 => CodecType::MP3, CodecId::Opus => CodecType::Opus, CodecId::Vorbis => CodecType::Vorbis,
+        CodecId::Flac => CodecType::FLAC, CodecId::Pcm => CodecType::PCM,
+        CodecId::WebVtt | CodecId::Subrip | CodecId::Unknown => return None,
+    })
+}
+
+/// Playability of a container MIME type, as answered by `canPlayType`.
+/// A bare container is only "maybe"; listing decodable codecs makes it "probably".
+pub fn type_support(mime: &str) -> CodecSupport {
+    let (essence, codecs) = parse_mime(mime);
+    let Some(format) = format_for_mime(&essence) else { return CodecSupport::Unsupported };
+    if codecs.is_empty() {
+        return CodecSupport::MaybeSupported;
     }
-    
-    // WebM/MKV: EBML header
-    if data[0..4] == [0x1A, 0x45, 0xDF, 0xA3] {
-        // Check DocType for webm vs matroska
-        if data.len() > 30 {
-            let s = String::from_utf8_lossy(&data[0..40]);
-            if s.contains("webm") { return Some(ContainerFormat::WebM); }
-            if s.contains("matroska") { return Some(ContainerFormat::Mkv); }
+    let registry = CodecRegistry::new();
+    let audio_only = essence.starts_with("audio/");
+    for codec in &codecs {
+        let Some(id) = codec_from_string(codec) else { return CodecSupport::Unsupported };
+        let decodable = codec_type(id).map(|t| registry.is_decode_supported(t) == CodecSupport::Supported).unwrap_or(id == CodecId::WebVtt);
+        if !format.can_carry(id) || !decodable || (audio_only && id.is_video()) {
+            return CodecSupport::Unsupported;
         }
-        return Some(ContainerFormat::Mkv);
-    }
-    
-    // MPEG-TS: sync byte 0x47 every 188 bytes
-    if data[0] == 0x47 && (data.len() < 188 || data[188] == 0x47) {
-        return Some(ContainerFormat::MpegTs);
     }
-    
-    None
+    CodecSupport::Supported
 }
 
-/// Container format
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ContainerFormat { Mp4, WebM, Mkv, MpegTs, FragmentedMp4 }
-
 #[cfg(test)]
 mod tests {
     use super::*;
     
     #[test]
-    fn test_detect_mp4() {
-        let data = [0, 0, 0, 20, b'f', b't', b'y', b'p', b'm', b'p', b'4', b'2'];
-        assert_eq!(detect_format(&data), Some(ContainerFormat::Mp4));
-    }
-    
-    #[test]    
-    fn test_detect_ebml() {
-        let data = [0x1A, 0x45, 0xDF, 0xA3, 0, 0, 0, 0, 0, 0, 0, 0];
-        assert_eq!(detect_format(&data), Some(ContainerFormat::Mkv));
+    fn test_type_support() {
+        assert_eq!(type_support("video/mp4"), CodecSupport::MaybeSupported);
+        assert_eq!(type_support("video/mp4; codecs=\"avc1.42E01E, mp4a.40.2\""), CodecSupport::Supported);
+        assert_eq!(type_support("video/webm; codecs=\"av01.0.05M.08, opus\""), CodecSupport::Supported);
+        assert_eq!(type_support("video/webm; codecs=\"vp8, opus\""), CodecSupport::Unsupported);
+        assert_eq!(type_support("video/webm; codecs=\"vp9, opus\""), CodecSupport::Unsupported);
+        assert_eq!(type_support("video/webm; codecs=\"avc1.42E01E\""), CodecSupport::Unsupported);
+        assert_eq!(type_support("audio/mp4; codecs=\"avc1.42E01E\""), CodecSupport::Unsupported);
+        assert_eq!(type_support("video/unknown"), CodecSupport::Unsupported);
     }
 }
@@ -0,0 +1,468 @@
+//! FLAC Decoder
+//!
+//! Pure-Rust implementation of FLAC decoding. Every container hands over
+//! whole frames, and native `.flac` streams are cut into frames by decoding
+//! them in sequence. Header CRC-8 and frame CRC-16 are verified.
+
+use super::AudioDecoderState;
+use crate::decoders::{AudioSamples, EncodedPacket, DecoderResult, DecoderError, AudioDecoderTrait};
+use std::time::Duration;
+
+/// STREAMINFO metadata block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub min_block_size: u16,
+    pub max_block_size: u16,
+    pub min_frame_size: u32,
+    pub max_frame_size: u32,
+    pub sample_rate: u32,
+    pub channels: u8,
+    pub bits_per_sample: u8,
+    /// Samples per channel, 0 if unknown
+    pub total_samples: u64,
+    pub md5: [u8; 16],
+}
+
+impl StreamInfo {
+    /// Parse the 34-byte block body
+    pub fn parse(data: &[u8]) -> DecoderResult<Self> {
+        if data.len() < 34 { return Err(DecoderError::NeedMoreData); }
+        let mut r = BitReader::new(data);
+        let info = Self {
+            min_block_size: r.read(16)? as u16,
+            max_block_size: r.read(16)? as u16,
+            min_frame_size: r.read(24)?,
+            max_frame_size: r.read(24)?,
+            sample_rate: r.read(20)?,
+            channels: r.read(3)? as u8 + 1,
+            bits_per_sample: r.read(5)? as u8 + 1,
+            total_samples: ((r.read(4)? as u64) << 32) | r.read(32)? as u64,
+            md5: data[18..34].try_into().unwrap_or_default(),
+        };
+        if info.sample_rate == 0 || info.bits_per_sample < 4 {
+            return Err(DecoderError::InvalidBitstream("Invalid STREAMINFO".into()));
+        }
+        Ok(info)
+    }
+}
+
+/// STREAMINFO from `fLaC` followed by metadata blocks (Matroska, Ogg and
+/// native streams), or from a bare block body; also returns the length of
+/// the stream header
+pub fn parse_stream_header(data: &[u8]) -> DecoderResult<(StreamInfo, usize)> {
+    let Some(mut blocks) = data.strip_prefix(b"fLaC") else {
+        return Ok((StreamInfo::parse(data)?, data.len()));
+    };
+    let mut info = None;
+    loop {
+        if blocks.len() < 4 { return Err(DecoderError::NeedMoreData); }
+        let last = blocks[0] & 0x80 != 0;
+        let kind = blocks[0] & 0x7F;
+        let len = u32::from_be_bytes([0, blocks[1], blocks[2], blocks[3]]) as usize;
+        let body = blocks.get(4..4 + len).ok_or(DecoderError::NeedMoreData)?;
+        if kind == 0 {
+            info = Some(StreamInfo::parse(body)?);
+        }
+        blocks = &blocks[4 + len..];
+        if last { break; }
+    }
+    let info = info.ok_or_else(|| DecoderError::InvalidBitstream("No STREAMINFO".into()))?;
+    Ok((info, data.len() - blocks.len()))
+}
+
+/// Inter-channel decorrelation of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChannelAssignment {
+    Independent(u8),
+    LeftSide,
+    SideRight,
+    MidSide,
+}
+
+/// FLAC Decoder
+#[derive(Debug)]
+pub struct FlacDecoder {
+    state: AudioDecoderState,
+    info: Option<StreamInfo>,
+    /// Per-channel samples of the current frame
+    channels: Vec<Vec<i32>>,
+}
+
+impl FlacDecoder {
+    pub fn new() -> Self {
+        Self {
+            state: AudioDecoderState { sample_rate: 44100, channels: 2, samples_decoded: 0 },
+            info: None,
+            channels: Vec::new(),
+        }
+    }
+
+    pub fn stream_info(&self) -> Option<&StreamInfo> {
+        self.info.as_ref()
+    }
+
+    /// Decode the frame at the start of `data`; returns the samples and the
+    /// frame's length
+    pub fn decode_frame(&mut self, data: &[u8], pts: Duration) -> DecoderResult<(AudioSamples, usize)> {
+        let mut r = BitReader::new(data);
+        if r.read(15)? != 0x7FFC { return Err(DecoderError::InvalidBitstream("Missing frame sync".into())); }
+        // Blocking strategy
+        r.read(1)?;
+        let block_size_code = r.read(4)?;
+        let rate_code = r.read(4)?;
+        let assignment = match r.read(4)? {
+            n @ 0..=7 => ChannelAssignment::Independent(n as u8 + 1),
+            8 => ChannelAssignment::LeftSide,
+            9 => ChannelAssignment::SideRight,
+            10 => ChannelAssignment::MidSide,
+            _ => return Err(DecoderError::InvalidBitstream("Reserved channel assignment".into())),
+        };
+        let bps = match r.read(3)? {
+            0 => self.info.as_ref().map(|i| i.bits_per_sample as u32)
+                .ok_or_else(|| DecoderError::InvalidBitstream("Sample size needs STREAMINFO".into()))?,
+            1 => 8, 2 => 12, 4 => 16, 5 => 20, 6 => 24, 7 => 32,
+            _ => return Err(DecoderError::InvalidBitstream("Reserved sample size".into())),
+        };
+        r.read(1)?;
+        // Frame or sample number, UTF-8 style
+        let first = r.read(8)?;
+        let extra = (first as u8).leading_ones();
+        if extra == 1 || extra > 7 { return Err(DecoderError::InvalidBitstream("Invalid coded number".into())); }
+        for _ in 1..extra {
+            if r.read(8)? & 0xC0 != 0x80 { return Err(DecoderError::InvalidBitstream("Invalid coded number".into())); }
+        }
+        let block_size = match block_size_code {
+            0 => return Err(DecoderError::InvalidBitstream("Reserved block size".into())),
+            1 => 192,
+            2..=5 => 576 << (block_size_code - 2),
+            6 => r.read(8)? + 1,
+            7 => r.read(16)? + 1,
+            n => 256 << (n - 8),
+        } as usize;
+        let sample_rate = match rate_code {
+            0 => self.info.as_ref().map(|i| i.sample_rate)
+                .ok_or_else(|| DecoderError::InvalidBitstream("Sample rate needs STREAMINFO".into()))?,
+            1 => 88200, 2 => 176400, 3 => 192000, 4 => 8000, 5 => 16000, 6 => 22050,
+            7 => 24000, 8 => 32000, 9 => 44100, 10 => 48000, 11 => 96000,
+            12 => r.read(8)? * 1000,
+            13 => r.read(16)?,
+            14 => r.read(16)? * 10,
+            _ => return Err(DecoderError::InvalidBitstream("Invalid sample rate".into())),
+        };
+        let header_len = r.byte_pos();
+        if r.read(8)? as u8 != crc8(&data[..header_len]) {
+            return Err(DecoderError::InvalidBitstream("Frame header CRC mismatch".into()));
+        }
+
+        let channel_count = match assignment {
+            ChannelAssignment::Independent(n) => n as usize,
+            _ => 2,
+        };
+        self.channels.resize_with(channel_count, Vec::new);
+        for (ch, samples) in self.channels.iter_mut().enumerate() {
+            // The side channel carries one more bit
+            let side = matches!((assignment, ch), (ChannelAssignment::LeftSide, 1) | (ChannelAssignment::SideRight, 0) | (ChannelAssignment::MidSide, 1));
+            samples.clear();
+            samples.resize(block_size, 0);
+            decode_subframe(&mut r, bps + side as u32, samples)?;
+        }
+        r.align();
+        let frame_len = r.byte_pos();
+        let crc = r.read(16)? as u16;
+        if crc != crc16(&data[..frame_len]) {
+            return Err(DecoderError::InvalidBitstream("Frame CRC mismatch".into()));
+        }
+
+        if let [a, b] = &mut self.channels[..] {
+            for (a, b) in a.iter_mut().zip(b.iter_mut()) {
+                match assignment {
+                    ChannelAssignment::LeftSide => *b = a.wrapping_sub(*b),
+                    ChannelAssignment::SideRight => *a = a.wrapping_add(*b),
+                    ChannelAssignment::MidSide => {
+                        let mid = (*a as i64) << 1 | (*b as i64 & 1);
+                        let side = *b as i64;
+                        *a = ((mid + side) >> 1) as i32;
+                        *b = ((mid - side) >> 1) as i32;
+                    }
+                    ChannelAssignment::Independent(_) => {}
+                }
+            }
+        }
+
+        let scale = 1.0 / (1u64 << (bps - 1)) as f32;
+        let mut out = Vec::with_capacity(block_size * channel_count);
+        for i in 0..block_size {
+            out.extend(self.channels.iter().map(|ch| ch[i] as f32 * scale));
+        }
+        self.state.sample_rate = sample_rate;
+        self.state.channels = channel_count as u32;
+        self.state.samples_decoded += block_size as u64;
+        let duration = Duration::from_secs_f64(block_size as f64 / sample_rate as f64);
+        Ok((AudioSamples { pts, duration, sample_rate, channels: channel_count as u32, data: out }, frame_len + 2))
+    }
+}
+
+impl Default for FlacDecoder { fn default() -> Self { Self::new() } }
+
+impl AudioDecoderTrait for FlacDecoder {
+    fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<AudioSamples> {
+        // Ogg FLAC mapping: the first packet carries the stream header
+        if packet.data.starts_with(b"\x7FFLAC") && packet.data.len() >= 13 {
+            self.configure(&packet.data[9..])?;
+            return Ok(AudioSamples { pts: packet.pts, duration: Duration::ZERO, sample_rate: self.state.sample_rate, channels: self.state.channels, data: Vec::new() });
+        }
+        // Other metadata blocks
+        if packet.data.first().is_some_and(|&b| b != 0xFF) {
+            return Ok(AudioSamples { pts: packet.pts, duration: Duration::ZERO, sample_rate: self.state.sample_rate, channels: self.state.channels, data: Vec::new() });
+        }
+        self.decode_frame(&packet.data, packet.pts).map(|(samples, _)| samples)
+    }
+    fn flush(&mut self) -> Option<AudioSamples> { None }
+    fn reset(&mut self) { self.state.samples_decoded = 0; }
+    fn sample_rate(&self) -> u32 { self.state.sample_rate }
+    fn channels(&self) -> u32 { self.state.channels }
+    fn configure(&mut self, extradata: &[u8]) -> DecoderResult<()> {
+        let (info, _) = parse_stream_header(extradata)?;
+        self.state.sample_rate = info.sample_rate;
+        self.state.channels = info.channels as u32;
+        self.info = Some(info);
+        Ok(())
+    }
+}
+
+fn decode_subframe(r: &mut BitReader, bps: u32, out: &mut [i32]) -> DecoderResult<()> {
+    if r.read(1)? != 0 { return Err(DecoderError::InvalidBitstream("Subframe padding bit set".into())); }
+    let kind = r.read(6)?;
+    let wasted = if r.read(1)? == 1 { r.read_unary()? + 1 } else { 0 };
+    if wasted >= bps { return Err(DecoderError::InvalidBitstream("Too many wasted bits".into())); }
+    let bps = bps - wasted;
+    match kind {
+        0 => out.fill(r.read_signed(bps)?),
+        1 => for s in out.iter_mut() { *s = r.read_signed(bps)?; },
+        8..=12 => {
+            let order = (kind - 8) as usize;
+            if order > out.len() { return Err(DecoderError::InvalidBitstream("Predictor order exceeds block".into())); }
+            for s in &mut out[..order] { *s = r.read_signed(bps)?; }
+            decode_residual(r, order, out)?;
+            restore_fixed(order, out);
+        }
+        32..=63 => {
+            let order = (kind - 31) as usize;
+            if order > out.len() { return Err(DecoderError::InvalidBitstream("Predictor order exceeds block".into())); }
+            for s in &mut out[..order] { *s = r.read_signed(bps)?; }
+            let precision = r.read(4)? + 1;
+            if precision == 16 { return Err(DecoderError::InvalidBitstream("Invalid coefficient precision".into())); }
+            let shift = r.read_signed(5)?;
+            if shift < 0 { return Err(DecoderError::InvalidBitstream("Negative LPC shift".into())); }
+            let mut coefs = [0i32; 32];
+            for c in &mut coefs[..order] { *c = r.read_signed(precision)?; }
+            decode_residual(r, order, out)?;
+            restore_lpc(&coefs[..order], shift as u32, out);
+        }
+        _ => return Err(DecoderError::InvalidBitstream("Reserved subframe type".into())),
+    }
+    if wasted > 0 {
+        for s in out.iter_mut() { *s = s.wrapping_shl(wasted); }
+    }
+    Ok(())
+}
+
+/// Rice-coded residual into `out[order..]`
+fn decode_residual(r: &mut BitReader, order: usize, out: &mut [i32]) -> DecoderResult<()> {
+    let param_bits = match r.read(2)? {
+        0 => 4,
+        1 => 5,
+        _ => return Err(DecoderError::InvalidBitstream("Reserved residual coding".into())),
+    };
+    let partition_order = r.read(4)?;
+    let partitions = 1usize << partition_order;
+    let partition_len = out.len() >> partition_order;
+    if partition_len << partition_order != out.len() || partition_len < order {
+        return Err(DecoderError::InvalidBitstream("Invalid partition order".into()));
+    }
+    let escape = (1 << param_bits) - 1;
+    let mut pos = order;
+    for p in 0..partitions {
+        let end = (p + 1) * partition_len;
+        let param = r.read(param_bits)?;
+        if param == escape {
+            let bits = r.read(5)?;
+            for s in &mut out[pos..end] { *s = if bits == 0 { 0 } else { r.read_signed(bits)? }; }
+        } else {
+            for s in &mut out[pos..end] {
+                let q = r.read_unary()?;
+                let value = (q << param) | r.read(param)?;
+                *s = ((value >> 1) as i32) ^ -((value & 1) as i32);
+            }
+        }
+        pos = end;
+    }
+    Ok(())
+}
+
+fn restore_fixed(order: usize, out: &mut [i32]) {
+    for i in order..out.len() {
+        let p = match order {
+            0 => 0,
+            1 => out[i - 1],
+            2 => 2i32.wrapping_mul(out[i - 1]).wrapping_sub(out[i - 2]),
+            3 => 3i32.wrapping_mul(out[i - 1].wrapping_sub(out[i - 2])).wrapping_add(out[i - 3]),
+            _ => 4i32.wrapping_mul(out[i - 1].wrapping_add(out[i - 3]))
+                .wrapping_sub(6i32.wrapping_mul(out[i - 2]))
+                .wrapping_sub(out[i - 4]),
+        };
+        out[i] = out[i].wrapping_add(p);
+    }
+}
+
+fn restore_lpc(coefs: &[i32], shift: u32, out: &mut [i32]) {
+    let order = coefs.len();
+    for i in order..out.len() {
+        let prediction: i64 = coefs.iter().zip(out[i - order..i].iter().rev())
+            .map(|(&c, &s)| c as i64 * s as i64)
+            .sum();
+        out[i] = out[i].wrapping_add((prediction >> shift) as i32);
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &b| {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &b| {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+/// MSB-first bit reader
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self { Self { data, pos: 0 } }
+
+    fn byte_pos(&self) -> usize { self.pos / 8 }
+
+    fn align(&mut self) { self.pos = self.pos.div_ceil(8) * 8; }
+
+    fn read(&mut self, bits: u32) -> DecoderResult<u32> {
+        if bits == 0 { return Ok(0); }
+        let mut value = 0u64;
+        let mut remaining = bits;
+        while remaining > 0 {
+            let byte = *self.data.get(self.pos / 8).ok_or(DecoderError::NeedMoreData)?;
+            let offset = (self.pos % 8) as u32;
+            let take = remaining.min(8 - offset);
+            let chunk = (byte as u32 >> (8 - offset - take)) & ((1 << take) - 1);
+            value = (value << take) | chunk as u64;
+            remaining -= take;
+            self.pos += take as usize;
+        }
+        Ok(value as u32)
+    }
+
+    fn read_signed(&mut self, bits: u32) -> DecoderResult<i32> {
+        let value = self.read(bits)?;
+        Ok(((value << (32 - bits)) as i32) >> (32 - bits))
+    }
+
+    /// Zeros before the next one bit
+    fn read_unary(&mut self) -> DecoderResult<u32> {
+        let mut count = 0;
+        loop {
+            let byte = *self.data.get(self.pos / 8).ok_or(DecoderError::NeedMoreData)?;
+            let offset = self.pos % 8;
+            let rest = byte << offset;
+            if rest != 0 {
+                let zeros = rest.leading_zeros();
+                self.pos += zeros as usize + 1;
+                return Ok(count + zeros);
+            }
+            count += 8 - offset as u32;
+            self.pos += 8 - offset;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bit writer for building frames
+    struct Bits(Vec<u8>, u32);
+    impl Bits {
+        fn put(&mut self, bits: u32, value: u32) {
+            for i in (0..bits).rev() {
+                if self.1.is_multiple_of(8) { self.0.push(0); }
+                let last = self.0.last_mut().unwrap();
+                *last |= (((value >> i) & 1) as u8) << (7 - self.1 % 8);
+                self.1 += 1;
+            }
+        }
+    }
+
+    /// Stereo 16-bit frame of 16 samples: left verbatim ramp, side a
+    /// fixed-order-1 constant step with Rice residual
+    fn frame() -> Vec<u8> {
+        let mut b = Bits(Vec::new(), 0);
+        b.put(15, 0x7FFC); b.put(1, 0);
+        b.put(4, 6); b.put(4, 9); b.put(4, 8); b.put(3, 4); b.put(1, 0);
+        b.put(8, 0); // frame number
+        b.put(8, 15); // block size - 1
+        let crc = crc8(&b.0);
+        b.put(8, crc as u32);
+        // Left: verbatim 0, 100, ..., 1500
+        b.put(1, 0); b.put(6, 1); b.put(1, 0);
+        for i in 0..16 { b.put(16, i * 100); }
+        // Side (17 bits): fixed order 1, warm-up -5, residuals 0 with parameter 0
+        b.put(1, 0); b.put(6, 9); b.put(1, 0);
+        b.put(17, (-5i32 as u32) & 0x1FFFF);
+        b.put(2, 0); b.put(4, 0); b.put(4, 0);
+        for _ in 1..16 { b.put(1, 1); }
+        while !b.1.is_multiple_of(8) { b.put(1, 0); }
+        let crc = crc16(&b.0);
+        b.put(16, crc as u32);
+        b.0
+    }
+
+    #[test]
+    fn test_decode_frame() {
+        let mut decoder = FlacDecoder::new();
+        let data = frame();
+        let (samples, len) = decoder.decode_frame(&data, Duration::ZERO).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!((samples.sample_rate, samples.channels, samples.data.len()), (44100, 2, 32));
+        // Right = left - side
+        assert_eq!(samples.data[2 * 3] * 32768.0, 300.0);
+        assert_eq!(samples.data[2 * 3 + 1] * 32768.0, 305.0);
+
+        let mut corrupt = data.clone();
+        corrupt[20] ^= 1;
+        assert!(decoder.decode_frame(&corrupt, Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_stream_header() {
+        let mut header = b"fLaC\x80\x00\x00\x22".to_vec();
+        header.extend_from_slice(&[0x10, 0x00, 0x10, 0x00, 0, 0, 0, 0, 0, 0]);
+        // 48000 Hz, 2 channels, 24 bits, 1000 samples
+        header.extend_from_slice(&[0x0B, 0xB8, 0x03, 0x70, 0x00, 0x00, 0x03, 0xE8]);
+        header.extend_from_slice(&[0; 16]);
+        let (info, len) = parse_stream_header(&header).unwrap();
+        assert_eq!(len, header.len());
+        assert_eq!((info.sample_rate, info.channels, info.bits_per_sample, info.total_samples), (48000, 2, 24, 1000));
+    }
+}
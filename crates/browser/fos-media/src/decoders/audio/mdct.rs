@@ -0,0 +1,209 @@
+//! Fast Inverse MDCT
+//!
+//! Mixed-radix complex FFT and an inverse MDCT built on it. The MDCT is
+//! computed through a DCT-IV of half the output length, which in turn runs
+//! as a complex FFT of a quarter of the output length. Vorbis block sizes are
+//! powers of two; Opus/CELT frames need radix 3 and 5 as well.
+
+use std::f64::consts::PI;
+
+/// Complex number
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub fn new(re: f32, im: f32) -> Self { Self { re, im } }
+
+    /// `e^(i·angle)`
+    fn expi(angle: f64) -> Self { Self::new(angle.cos() as f32, angle.sin() as f32) }
+
+    fn mul(self, o: Self) -> Self {
+        Self::new(self.re * o.re - self.im * o.im, self.re * o.im + self.im * o.re)
+    }
+
+    fn add(self, o: Self) -> Self { Self::new(self.re + o.re, self.im + o.im) }
+}
+
+/// Forward complex FFT of a fixed size
+#[derive(Debug, Clone)]
+pub struct Fft {
+    n: usize,
+    /// Radices, largest first
+    factors: Vec<usize>,
+    /// `e^(-2πik/n)`
+    twiddles: Vec<Complex>,
+}
+
+impl Fft {
+    pub fn new(n: usize) -> Self {
+        let mut factors = Vec::new();
+        let mut rest = n.max(1);
+        for p in [4, 2, 3, 5] {
+            while rest.is_multiple_of(p) {
+                factors.push(p);
+                rest /= p;
+            }
+        }
+        // Remaining prime factors use a plain DFT butterfly
+        let mut p = 7;
+        while rest > 1 {
+            while rest.is_multiple_of(p) {
+                factors.push(p);
+                rest /= p;
+            }
+            p += 2;
+        }
+        let twiddles = (0..n).map(|k| Complex::expi(-2.0 * PI * k as f64 / n as f64)).collect();
+        Self { n, factors, twiddles }
+    }
+
+    pub fn len(&self) -> usize { self.n }
+
+    pub fn is_empty(&self) -> bool { self.n == 0 }
+
+    /// `output[k] = Σ input[j]·e^(-2πijk/n)`
+    pub fn process(&self, input: &[Complex], output: &mut [Complex]) {
+        if self.n == 0 { return; }
+        self.recurse(input, 1, output, &self.factors);
+    }
+
+    fn recurse(&self, input: &[Complex], stride: usize, output: &mut [Complex], factors: &[usize]) {
+        let n = output.len();
+        let Some((&p, rest)) = factors.split_first() else {
+            output[0] = input[0];
+            return;
+        };
+        let m = n / p;
+        for q in 0..p {
+            self.recurse(&input[q * stride..], stride * p, &mut output[q * m..(q + 1) * m], rest);
+        }
+        // Twiddle step of size n within the full transform of size self.n
+        let step = self.n / n;
+        let mut scratch = [Complex::default(); 8];
+        let mut generic = Vec::new();
+        let values: &mut [Complex] = if p <= scratch.len() { &mut scratch[..p] } else { generic.resize(p, Complex::default()); &mut generic };
+        for k in 0..m {
+            for q in 0..p {
+                values[q] = output[q * m + k].mul(self.twiddles[(q * k * step) % self.n]);
+            }
+            match p {
+                2 => {
+                    let (a, b) = (values[0], values[1]);
+                    output[k] = a.add(b);
+                    output[m + k] = Complex::new(a.re - b.re, a.im - b.im);
+                }
+                4 => {
+                    let (a, b, c, d) = (values[0], values[1], values[2], values[3]);
+                    let (s0, s1) = (a.add(c), Complex::new(a.re - c.re, a.im - c.im));
+                    let (s2, s3) = (b.add(d), Complex::new(b.re - d.re, b.im - d.im));
+                    output[k] = s0.add(s2);
+                    output[2 * m + k] = Complex::new(s0.re - s2.re, s0.im - s2.im);
+                    // -i·s3
+                    output[m + k] = Complex::new(s1.re + s3.im, s1.im - s3.re);
+                    output[3 * m + k] = Complex::new(s1.re - s3.im, s1.im + s3.re);
+                }
+                _ => {
+                    for j in 0..p {
+                        let mut sum = Complex::default();
+                        for (q, v) in values.iter().enumerate() {
+                            sum = sum.add(v.mul(self.twiddles[(q * j * m * step) % self.n]));
+                        }
+                        output[j * m + k] = sum;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Inverse MDCT producing `n` samples from `n / 2` coefficients:
+/// `y[i] = Σ X[k]·cos(2π/n·(i + 1/2 + n/4)·(k + 1/2))`, unscaled
+#[derive(Debug, Clone)]
+pub struct Imdct {
+    n: usize,
+    fft: Fft,
+    pre: Vec<Complex>,
+    post: Vec<Complex>,
+}
+
+impl Imdct {
+    /// `n` must be a multiple of 4
+    pub fn new(n: usize) -> Self {
+        let m = n / 2;
+        let h = n / 4;
+        Self {
+            n,
+            fft: Fft::new(h),
+            pre: (0..h).map(|j| Complex::expi(-PI * (j as f64 + 0.25) / m as f64)).collect(),
+            post: (0..h).map(|k| Complex::expi(-PI * k as f64 / m as f64)).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize { self.n }
+
+    pub fn is_empty(&self) -> bool { self.n == 0 }
+
+    pub fn process(&self, input: &[f32], output: &mut [f32]) {
+        let (m, h) = (self.n / 2, self.n / 4);
+        // DCT-IV of the coefficients through an FFT of size n/4
+        let v: Vec<Complex> = (0..h).map(|j| Complex::new(input[2 * j], input[m - 1 - 2 * j]).mul(self.pre[j])).collect();
+        let mut spectrum = vec![Complex::default(); h];
+        self.fft.process(&v, &mut spectrum);
+        let mut u = vec![0.0f32; m];
+        for k in 0..h {
+            let w = spectrum[k].mul(self.post[k]);
+            u[2 * k] = w.re;
+            u[m - 1 - 2 * k] = -w.im;
+        }
+        // Unfold: y[i] = u(i + m/2) with u's even/odd symmetric extension
+        for (i, y) in output[..self.n].iter_mut().enumerate() {
+            let j = i + m / 2;
+            *y = if j < m {
+                u[j]
+            } else if j < 2 * m {
+                -u[2 * m - 1 - j]
+            } else {
+                -u[j - 2 * m]
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fft_mixed_radix() {
+        for n in [8, 60, 120, 14] {
+            let input: Vec<Complex> = (0..n).map(|i| Complex::new((i as f32 * 0.37).sin(), (i as f32 * 0.11).cos())).collect();
+            let mut output = vec![Complex::default(); n];
+            Fft::new(n).process(&input, &mut output);
+            for (k, out) in output.iter().enumerate() {
+                let mut expected = Complex::default();
+                for (j, x) in input.iter().enumerate() {
+                    expected = expected.add(x.mul(Complex::expi(-2.0 * PI * (j * k) as f64 / n as f64)));
+                }
+                assert!((out.re - expected.re).abs() < 1e-3 && (out.im - expected.im).abs() < 1e-3, "n={} k={}", n, k);
+            }
+        }
+    }
+
+    #[test]
+    fn test_imdct_matches_definition() {
+        for n in [16, 240] {
+            let input: Vec<f32> = (0..n / 2).map(|k| ((k * 7 % 11) as f32 - 5.0) / 5.0).collect();
+            let mut output = vec![0.0; n];
+            Imdct::new(n).process(&input, &mut output);
+            for (i, y) in output.iter().enumerate() {
+                let expected: f64 = input.iter().enumerate()
+                    .map(|(k, &x)| x as f64 * (2.0 * PI / n as f64 * (i as f64 + 0.5 + n as f64 / 4.0) * (k as f64 + 0.5)).cos())
+                    .sum();
+                assert!((*y as f64 - expected).abs() < 1e-3, "n={} i={}", n, i);
+            }
+        }
+    }
+}
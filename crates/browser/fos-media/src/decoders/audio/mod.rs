@@ -1,9 +1,12 @@
 //! Audio Decoders
 //!
-//! Pure-Rust implementations of AAC and Vorbis audio decoders.
+//! Pure-Rust implementations of AAC, Vorbis, FLAC and Opus audio decoders.
 
 pub mod aac;
 pub mod vorbis;
+pub mod flac;
+pub mod mdct;
+pub mod opus;
 
 use super::{AudioSamples, EncodedPacket, DecoderResult, DecoderError, AudioDecoderTrait};
 use std::time::Duration;
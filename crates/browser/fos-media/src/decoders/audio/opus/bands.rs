@@ -0,0 +1,905 @@
+//! CELT Band Shapes
+//!
+//! PVQ decoding of the normalised spectrum (RFC 6716 section 4.3.4): pulse
+//! vectors, recursive band splits, mid/side stereo, spectral folding and
+//! spreading, and the final denormalisation by the band energies.
+
+use super::range::{RangeDecoder, BITRES};
+use super::rate::{get_pulses, PulseCache, EBANDS, LOG_N, NB_EBANDS, QTHETA_OFFSET, QTHETA_OFFSET_TWOPHASE, SHORT_MDCT_SIZE};
+
+pub const SPREAD_NONE: i32 = 0;
+pub const SPREAD_NORMAL: i32 = 2;
+pub const SPREAD_AGGRESSIVE: i32 = 3;
+
+const EPSILON: f32 = 1e-15;
+
+/// Mean band energy in log2 units
+pub const E_MEANS: [f32; NB_EBANDS] = [
+    6.4375, 6.25, 5.75, 5.3125, 5.0625, 4.8125, 4.5, 4.375, 4.875, 4.6875, 4.5625, 4.4375, 4.875, 4.625, 4.3125, 4.5,
+    4.375, 4.625, 4.75, 4.4375, 3.75,
+];
+
+const ORDERY_TABLE: [usize; 30] = [1, 0, 3, 0, 2, 1, 7, 0, 4, 3, 6, 1, 5, 2, 15, 0, 8, 7, 12, 3, 11, 4, 14, 1, 9, 6, 13, 2, 10, 5];
+
+pub fn lcg_rand(seed: u32) -> u32 { seed.wrapping_mul(1664525).wrapping_add(1013904223) }
+
+/// `2^x` as computed by the float reference
+pub fn exp2(x: f32) -> f32 { (std::f64::consts::LN_2 * x as f64).exp() as f32 }
+
+fn frac_mul16(a: i32, b: i32) -> i32 { (16384 + (a as i16 as i32) * (b as i16 as i32)) >> 15 }
+
+fn ilog(v: u32) -> i32 { 32 - v.leading_zeros() as i32 }
+
+/// Platform-independent cosine used where it affects the bit allocation
+fn bitexact_cos(x: i16) -> i16 {
+    let x2 = ((4096 + x as i32 * x as i32) >> 13) as i16 as i32;
+    let x2 = ((32767 - x2) + frac_mul16(x2, -7651 + frac_mul16(x2, 8277 + frac_mul16(-626, x2)))) as i16;
+    x2 + 1
+}
+
+fn bitexact_log2tan(mut isin: i32, mut icos: i32) -> i32 {
+    let lc = ilog(icos as u32);
+    let ls = ilog(isin as u32);
+    icos <<= 15 - lc;
+    isin <<= 15 - ls;
+    (ls - lc) * (1 << 11) + frac_mul16(isin, frac_mul16(isin, -2597) + 7932) - frac_mul16(icos, frac_mul16(icos, -2597) + 7932)
+}
+
+fn isqrt32(mut val: u32) -> u32 {
+    let mut g = 0u32;
+    let mut bshift = (ilog(val) - 1) >> 1;
+    let mut b = 1u32 << bshift;
+    loop {
+        let t = ((g << 1) + b) << bshift;
+        if t <= val {
+            g += b;
+            val -= t;
+        }
+        b >>= 1;
+        bshift -= 1;
+        if bshift < 0 { break; }
+    }
+    g
+}
+
+fn unext(ui: &mut [u32], len: usize, mut ui0: u32) {
+    let mut j = 1;
+    loop {
+        let ui1 = ui[j].wrapping_add(ui[j - 1]).wrapping_add(ui0);
+        ui[j - 1] = ui0;
+        ui0 = ui1;
+        j += 1;
+        if j >= len { break; }
+    }
+    ui[j - 1] = ui0;
+}
+
+fn uprev(ui: &mut [u32], n: usize, mut ui0: u32) {
+    let mut j = 1;
+    loop {
+        let ui1 = ui[j].wrapping_sub(ui[j - 1]).wrapping_sub(ui0);
+        ui[j - 1] = ui0;
+        ui0 = ui1;
+        j += 1;
+        if j >= n { break; }
+    }
+    ui[j - 1] = ui0;
+}
+
+/// Row `n` of the PVQ codebook size recurrence: fills `u[0..=k+1]` and
+/// returns V(n, k)
+pub fn ncwrs_urow(n: usize, k: usize, u: &mut [u32]) -> u32 {
+    u[0] = 0;
+    u[1] = 1;
+    for (j, v) in u.iter_mut().enumerate().take(k + 2).skip(2) {
+        *v = (2 * j - 1) as u32;
+    }
+    for _ in 2..n {
+        unext(&mut u[1..], k + 1, 1);
+    }
+    u[k].wrapping_add(u[k + 1])
+}
+
+/// Pulse vector for codebook index `i`; returns its squared norm
+fn cwrsi(n: usize, mut k: usize, mut i: u32, y: &mut [i32], u: &mut [u32]) -> f32 {
+    let mut yy = 0.0f32;
+    for yj in y.iter_mut().take(n) {
+        let p = u[k + 1];
+        let s = -((i >= p) as i32);
+        i = i.wrapping_sub(p & s as u32);
+        let k0 = k as i32;
+        let mut p = u[k];
+        while p > i {
+            k -= 1;
+            p = u[k];
+        }
+        i -= p;
+        let val = ((k0 - k as i32 + s) ^ s) as i16;
+        *yj = val as i32;
+        yy += val as f32 * val as f32;
+        uprev(u, k + 2, 0);
+    }
+    yy
+}
+
+fn decode_pulses(y: &mut [i32], n: usize, k: usize, dec: &mut RangeDecoder) -> f32 {
+    let mut u = vec![0u32; k + 2];
+    let ft = ncwrs_urow(n, k, &mut u);
+    let index = dec.uint(ft);
+    cwrsi(n, k, index, y, &mut u)
+}
+
+fn exp_rotation1(x: &mut [f32], len: usize, stride: usize, c: f32, s: f32) {
+    let ms = -s;
+    for i in 0..len.saturating_sub(stride) {
+        let (x1, x2) = (x[i], x[i + stride]);
+        x[i + stride] = c * x2 + s * x1;
+        x[i] = c * x1 + ms * x2;
+    }
+    for i in (0..len as isize - 2 * stride as isize).rev() {
+        let i = i as usize;
+        let (x1, x2) = (x[i], x[i + stride]);
+        x[i + stride] = c * x2 + s * x1;
+        x[i] = c * x1 + ms * x2;
+    }
+}
+
+/// Spreading rotation applied to sparse pulse vectors
+fn exp_rotation(x: &mut [f32], len: usize, dir: i32, stride: usize, k: usize, spread: i32) {
+    const SPREAD_FACTOR: [usize; 3] = [15, 10, 5];
+    if 2 * k >= len || spread == SPREAD_NONE {
+        return;
+    }
+    let factor = SPREAD_FACTOR[spread as usize - 1];
+    let gain = len as f32 / (len + factor * k) as f32;
+    let theta = 0.5 * (gain * gain);
+    let cos_norm = |v: f32| ((0.5 * std::f32::consts::PI * v) as f64).cos() as f32;
+    let c = cos_norm(theta);
+    let s = cos_norm(1.0 - theta);
+    let mut stride2 = 0;
+    if len >= 8 * stride {
+        stride2 = 1;
+        while (stride2 * stride2 + stride2) * stride + (stride >> 2) < len {
+            stride2 += 1;
+        }
+    }
+    let len = len / stride;
+    for i in 0..stride {
+        let x = &mut x[i * len..];
+        if dir < 0 {
+            if stride2 != 0 {
+                exp_rotation1(x, len, stride2, s, c);
+            }
+            exp_rotation1(x, len, 1, c, s);
+        } else {
+            exp_rotation1(x, len, 1, c, -s);
+            if stride2 != 0 {
+                exp_rotation1(x, len, stride2, s, -c);
+            }
+        }
+    }
+}
+
+fn extract_collapse_mask(iy: &[i32], n: usize, b: usize) -> u32 {
+    if b <= 1 {
+        return 1;
+    }
+    let n0 = n / b;
+    let mut mask = 0;
+    for i in 0..b {
+        if iy[i * n0..(i + 1) * n0].iter().any(|&v| v != 0) {
+            mask |= 1 << i;
+        }
+    }
+    mask
+}
+
+/// Decode a unit-norm PVQ vector of `k` pulses scaled by `gain`
+fn alg_unquant(x: &mut [f32], n: usize, k: usize, spread: i32, b: usize, dec: &mut RangeDecoder, gain: f32) -> u32 {
+    let mut iy = vec![0i32; n];
+    let ryy = decode_pulses(&mut iy, n, k, dec);
+    let g = 1.0 / ryy.sqrt() * gain;
+    for (x, &y) in x.iter_mut().zip(&iy) {
+        *x = g * y as f32;
+    }
+    exp_rotation(x, n, -1, b, k, spread);
+    extract_collapse_mask(&iy, n, b)
+}
+
+pub fn renormalise_vector(x: &mut [f32], gain: f32) {
+    let e = EPSILON + x.iter().fold(0.0f32, |acc, &v| acc + v * v);
+    let g = 1.0 / e.sqrt() * gain;
+    for v in x {
+        *v *= g;
+    }
+}
+
+fn haar1(x: &mut [f32], n0: usize, stride: usize) {
+    const S: f32 = std::f32::consts::FRAC_1_SQRT_2;
+    for i in 0..stride {
+        for j in 0..n0 >> 1 {
+            let tmp1 = S * x[stride * 2 * j + i];
+            let tmp2 = S * x[stride * (2 * j + 1) + i];
+            x[stride * 2 * j + i] = tmp1 + tmp2;
+            x[stride * (2 * j + 1) + i] = tmp1 - tmp2;
+        }
+    }
+}
+
+fn deinterleave_hadamard(x: &mut [f32], n0: usize, stride: usize, hadamard: bool) {
+    let mut tmp = vec![0.0f32; n0 * stride];
+    for i in 0..stride {
+        let row = if hadamard { ORDERY_TABLE[stride - 2 + i] } else { i };
+        for j in 0..n0 {
+            tmp[row * n0 + j] = x[j * stride + i];
+        }
+    }
+    x[..tmp.len()].copy_from_slice(&tmp);
+}
+
+fn interleave_hadamard(x: &mut [f32], n0: usize, stride: usize, hadamard: bool) {
+    let mut tmp = vec![0.0f32; n0 * stride];
+    for i in 0..stride {
+        let row = if hadamard { ORDERY_TABLE[stride - 2 + i] } else { i };
+        for j in 0..n0 {
+            tmp[j * stride + i] = x[row * n0 + j];
+        }
+    }
+    x[..tmp.len()].copy_from_slice(&tmp);
+}
+
+/// Resolution of the split angle
+fn compute_qn(n: i32, b: i32, offset: i32, pulse_cap: i32, stereo: bool) -> i32 {
+    const EXP2_TABLE8: [i32; 8] = [16384, 17866, 19483, 21247, 23170, 25267, 27554, 30048];
+    let mut n2 = 2 * n - 1;
+    if stereo && n == 2 {
+        n2 -= 1;
+    }
+    let qb = ((b + n2 * offset) / n2).min(b - pulse_cap - (4 << BITRES)).min(8 << BITRES);
+    if qb < (1 << BITRES >> 1) {
+        1
+    } else {
+        let qn = EXP2_TABLE8[(qb & 7) as usize] >> (14 - (qb >> BITRES));
+        (qn + 1) >> 1 << 1
+    }
+}
+
+struct BandCtx<'a, 'b> {
+    dec: &'a mut RangeDecoder<'b>,
+    cache: &'a PulseCache,
+    i: usize,
+    intensity: usize,
+    spread: i32,
+    tf_change: i32,
+    remaining_bits: i32,
+    seed: u32,
+    disable_inv: bool,
+}
+
+struct Split {
+    inv: bool,
+    imid: i32,
+    iside: i32,
+    delta: i32,
+    itheta: i32,
+    qalloc: i32,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn compute_theta(ctx: &mut BandCtx, n: i32, b: &mut i32, bb: i32, b0: i32, lm: i32, stereo: bool, fill: &mut u32) -> Split {
+    let pulse_cap = LOG_N[ctx.i] as i32 + lm * (1 << BITRES);
+    let offset = (pulse_cap >> 1) - if stereo && n == 2 { QTHETA_OFFSET_TWOPHASE } else { QTHETA_OFFSET };
+    let mut qn = compute_qn(n, *b, offset, pulse_cap, stereo);
+    if stereo && ctx.i >= ctx.intensity {
+        qn = 1;
+    }
+    let tell = ctx.dec.tell_frac() as i32;
+    let mut itheta = 0;
+    let mut inv = false;
+    let dec = &mut *ctx.dec;
+    if qn != 1 {
+        if stereo && n > 2 {
+            // Step pdf
+            let p0 = 3u32;
+            let x0 = qn as u32 / 2;
+            let ft = p0 * (x0 + 1) + x0;
+            let fs = dec.decode(ft);
+            let x = if fs < (x0 + 1) * p0 { fs / p0 } else { x0 + 1 + (fs - (x0 + 1) * p0) };
+            let (fl, fh) = if x <= x0 { (p0 * x, p0 * (x + 1)) } else { ((x - 1 - x0) + (x0 + 1) * p0, (x - x0) + (x0 + 1) * p0) };
+            dec.update(fl, fh, ft);
+            itheta = x as i32;
+        } else if b0 > 1 || stereo {
+            itheta = dec.uint(qn as u32 + 1) as i32;
+        } else {
+            // Triangular pdf
+            let half = (qn >> 1) as u32;
+            let ft = (half + 1) * (half + 1);
+            let fm = dec.decode(ft);
+            let qn = qn as u32;
+            let (theta, fl, fs) = if fm < ((half * (half + 1)) >> 1) {
+                let t = (isqrt32(8 * fm + 1) - 1) >> 1;
+                (t, (t * (t + 1)) >> 1, t + 1)
+            } else {
+                let t = (2 * (qn + 1) - isqrt32(8 * (ft - fm - 1) + 1)) >> 1;
+                (t, ft - (((qn + 1 - t) * (qn + 2 - t)) >> 1), qn + 1 - t)
+            };
+            dec.update(fl, fl + fs, ft);
+            itheta = theta as i32;
+        }
+        itheta = ((itheta as u32 * 16384) / qn as u32) as i32;
+    } else if stereo {
+        if *b > 2 << BITRES && ctx.remaining_bits > 2 << BITRES {
+            inv = dec.bit_logp(2);
+        }
+        if ctx.disable_inv {
+            inv = false;
+        }
+        itheta = 0;
+    }
+    let qalloc = ctx.dec.tell_frac() as i32 - tell;
+    *b -= qalloc;
+
+    let (imid, iside, delta) = if itheta == 0 {
+        *fill &= (1 << bb) - 1;
+        (32767, 0, -16384)
+    } else if itheta == 16384 {
+        *fill &= ((1 << bb) - 1) << bb;
+        (0, 32767, 16384)
+    } else {
+        let imid = bitexact_cos(itheta as i16) as i32;
+        let iside = bitexact_cos((16384 - itheta) as i16) as i32;
+        (imid, iside, frac_mul16((n - 1) << 7, bitexact_log2tan(iside, imid)))
+    };
+    Split { inv, imid, iside, delta, itheta, qalloc }
+}
+
+fn quant_band_n1(ctx: &mut BandCtx, x: &mut [f32], y: Option<&mut [f32]>, lowband_out: Option<&mut [f32]>) -> u32 {
+    fn decode_sign(ctx: &mut BandCtx, v: &mut f32) {
+        let mut sign = 0;
+        if ctx.remaining_bits >= 1 << BITRES {
+            sign = ctx.dec.bits(1);
+            ctx.remaining_bits -= 1 << BITRES;
+        }
+        *v = if sign != 0 { -1.0 } else { 1.0 };
+    }
+    decode_sign(ctx, &mut x[0]);
+    if let Some(y) = y {
+        decode_sign(ctx, &mut y[0]);
+    }
+    if let Some(out) = lowband_out {
+        out[0] = x[0];
+    }
+    1
+}
+
+#[allow(clippy::too_many_arguments)]
+fn quant_partition(
+    ctx: &mut BandCtx,
+    x: &mut [f32],
+    n: usize,
+    mut b: i32,
+    mut bb: i32,
+    lowband: Option<&[f32]>,
+    mut lm: i32,
+    gain: f32,
+    mut fill: u32,
+) -> u32 {
+    let b0 = bb;
+    if lm != -1 && b > ctx.cache.split_threshold(ctx.i, lm) + 12 && n > 2 {
+        let n = n >> 1;
+        let (xs, ys) = x[..2 * n].split_at_mut(n);
+        lm -= 1;
+        if bb == 1 {
+            fill = (fill & 1) | (fill << 1);
+        }
+        bb = (bb + 1) >> 1;
+        let split = compute_theta(ctx, n as i32, &mut b, bb, b0, lm, false, &mut fill);
+        let mid = (1.0 / 32768.0) * split.imid as f32;
+        let side = (1.0 / 32768.0) * split.iside as f32;
+        let itheta = split.itheta;
+        let mut delta = split.delta;
+        // Give more bits to low-energy MDCTs than they would otherwise deserve
+        if b0 > 1 && itheta & 0x3fff != 0 {
+            if itheta > 8192 {
+                delta -= delta >> (4 - lm);
+            } else {
+                delta = (delta + ((n as i32) << BITRES >> (5 - lm))).min(0);
+            }
+        }
+        let mut mbits = b.min((b - delta) / 2).max(0);
+        let mut sbits = b - mbits;
+        ctx.remaining_bits -= split.qalloc;
+        let next_lowband2 = lowband.map(|l| &l[n..]);
+        let mut rebalance = ctx.remaining_bits;
+        let mut cm;
+        if mbits >= sbits {
+            cm = quant_partition(ctx, xs, n, mbits, bb, lowband, lm, gain * mid, fill);
+            rebalance = mbits - (rebalance - ctx.remaining_bits);
+            if rebalance > 3 << BITRES && itheta != 0 {
+                sbits += rebalance - (3 << BITRES);
+            }
+            cm |= quant_partition(ctx, ys, n, sbits, bb, next_lowband2, lm, gain * side, fill >> bb) << (b0 >> 1);
+        } else {
+            cm = quant_partition(ctx, ys, n, sbits, bb, next_lowband2, lm, gain * side, fill >> bb) << (b0 >> 1);
+            rebalance = sbits - (rebalance - ctx.remaining_bits);
+            if rebalance > 3 << BITRES && itheta != 16384 {
+                mbits += rebalance - (3 << BITRES);
+            }
+            cm |= quant_partition(ctx, xs, n, mbits, bb, lowband, lm, gain * mid, fill);
+        }
+        return cm;
+    }
+
+    let i = ctx.i;
+    let mut q = ctx.cache.bits2pulses(i, lm, b);
+    let mut curr_bits = ctx.cache.pulses2bits(i, lm, q);
+    ctx.remaining_bits -= curr_bits;
+    // Never bust the budget
+    while ctx.remaining_bits < 0 && q > 0 {
+        ctx.remaining_bits += curr_bits;
+        q -= 1;
+        curr_bits = ctx.cache.pulses2bits(i, lm, q);
+        ctx.remaining_bits -= curr_bits;
+    }
+    let x = &mut x[..n];
+    if q != 0 {
+        return alg_unquant(x, n, get_pulses(q) as usize, ctx.spread, bb as usize, ctx.dec, gain);
+    }
+    // No pulses: fill the band with noise or a folded copy
+    let cm_mask = (1u32 << bb) - 1;
+    fill &= cm_mask;
+    if fill == 0 {
+        x.fill(0.0);
+        return 0;
+    }
+    let cm = match lowband {
+        None => {
+            for v in x.iter_mut() {
+                ctx.seed = lcg_rand(ctx.seed);
+                *v = (ctx.seed as i32 >> 20) as f32;
+            }
+            cm_mask
+        }
+        Some(lowband) => {
+            for (v, &l) in x.iter_mut().zip(lowband) {
+                ctx.seed = lcg_rand(ctx.seed);
+                // About 48 dB below the normal folding level
+                let tmp = if ctx.seed & 0x8000 != 0 { 1.0 / 256.0 } else { -1.0 / 256.0 };
+                *v = l + tmp;
+            }
+            fill
+        }
+    };
+    renormalise_vector(x, gain);
+    cm
+}
+
+#[allow(clippy::too_many_arguments)]
+fn quant_band(
+    ctx: &mut BandCtx,
+    x: &mut [f32],
+    n: usize,
+    b: i32,
+    mut bb: i32,
+    lowband: Option<&[f32]>,
+    lm: i32,
+    lowband_out: Option<&mut [f32]>,
+    gain: f32,
+    mut fill: u32,
+) -> u32 {
+    const BIT_INTERLEAVE: [u32; 16] = [0, 1, 1, 1, 2, 3, 3, 3, 2, 3, 3, 3, 2, 3, 3, 3];
+    const BIT_DEINTERLEAVE: [u32; 16] = [0x00, 0x03, 0x0C, 0x0F, 0x30, 0x33, 0x3C, 0x3F, 0xC0, 0xC3, 0xCC, 0xCF, 0xF0, 0xF3, 0xFC, 0xFF];
+    let n0 = n;
+    let mut n_b = n / bb as usize;
+    let long_blocks = bb == 1;
+    if n == 1 {
+        return quant_band_n1(ctx, x, None, lowband_out);
+    }
+    let mut tf_change = ctx.tf_change;
+    let recombine = tf_change.max(0) as usize;
+    // The folding source is transformed along with the band
+    let mut lowband = lowband.map(|l| l[..n].to_vec());
+
+    // Band recombining to increase frequency resolution
+    for k in 0..recombine {
+        if let Some(lowband) = &mut lowband {
+            haar1(lowband, n >> k, 1 << k);
+        }
+        fill = BIT_INTERLEAVE[(fill & 0xF) as usize] | BIT_INTERLEAVE[(fill >> 4) as usize] << 2;
+    }
+    bb >>= recombine;
+    n_b <<= recombine;
+
+    // Increasing the time resolution
+    let mut time_divide = 0;
+    while n_b & 1 == 0 && tf_change < 0 {
+        if let Some(lowband) = &mut lowband {
+            haar1(lowband, n_b, bb as usize);
+        }
+        fill |= fill << bb;
+        bb <<= 1;
+        n_b >>= 1;
+        time_divide += 1;
+        tf_change += 1;
+    }
+    let b0 = bb;
+    let n_b0 = n_b;
+
+    // Reorganize the samples in time order instead of frequency order
+    if b0 > 1 {
+        if let Some(lowband) = &mut lowband {
+            deinterleave_hadamard(lowband, n_b >> recombine, (b0 as usize) << recombine, long_blocks);
+        }
+    }
+
+    let mut cm = quant_partition(ctx, x, n, b, bb, lowband.as_deref(), lm, gain, fill);
+
+    if b0 > 1 {
+        interleave_hadamard(x, n_b >> recombine, (b0 as usize) << recombine, long_blocks);
+    }
+    n_b = n_b0;
+    bb = b0;
+    for _ in 0..time_divide {
+        bb >>= 1;
+        n_b <<= 1;
+        cm |= cm >> bb;
+        haar1(x, n_b, bb as usize);
+    }
+    for k in 0..recombine {
+        cm = BIT_DEINTERLEAVE[cm as usize];
+        haar1(x, n0 >> k, 1 << k);
+    }
+    bb <<= recombine;
+
+    // Scale output for later folding
+    if let Some(out) = lowband_out {
+        let scale = (n0 as f32).sqrt();
+        for (o, &v) in out[..n0].iter_mut().zip(x.iter()) {
+            *o = scale * v;
+        }
+    }
+    cm & ((1 << bb) - 1)
+}
+
+fn stereo_merge(x: &mut [f32], y: &mut [f32], mid: f32) {
+    let (mut xp, mut side) = (0.0f32, 0.0f32);
+    for (&xv, &yv) in x.iter().zip(y.iter()) {
+        xp += yv * xv;
+        side += yv * yv;
+    }
+    // Compensating for the mid normalization
+    let xp = mid * xp;
+    let el = mid * mid + side - 2.0 * xp;
+    let er = mid * mid + side + 2.0 * xp;
+    if er < 6e-4 || el < 6e-4 {
+        y.copy_from_slice(x);
+        return;
+    }
+    let lgain = 1.0 / el.sqrt();
+    let rgain = 1.0 / er.sqrt();
+    for (xv, yv) in x.iter_mut().zip(y.iter_mut()) {
+        let l = mid * *xv;
+        let r = *yv;
+        *xv = lgain * (l - r);
+        *yv = rgain * (l + r);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn quant_band_stereo(
+    ctx: &mut BandCtx,
+    x: &mut [f32],
+    y: &mut [f32],
+    n: usize,
+    mut b: i32,
+    bb: i32,
+    lowband: Option<&[f32]>,
+    lm: i32,
+    lowband_out: Option<&mut [f32]>,
+    mut fill: u32,
+) -> u32 {
+    if n == 1 {
+        return quant_band_n1(ctx, x, Some(y), lowband_out);
+    }
+    let orig_fill = fill;
+    let split = compute_theta(ctx, n as i32, &mut b, bb, bb, lm, true, &mut fill);
+    let mid = (1.0 / 32768.0) * split.imid as f32;
+    let side = (1.0 / 32768.0) * split.iside as f32;
+    let itheta = split.itheta;
+    let cm;
+
+    if n == 2 {
+        // Mid and side are orthogonal, so the side only needs a sign
+        let mut mbits = b;
+        let sbits = if itheta != 0 && itheta != 16384 { 1 << BITRES } else { 0 };
+        mbits -= sbits;
+        ctx.remaining_bits -= split.qalloc + sbits;
+        let mut sign = 0;
+        if sbits != 0 {
+            sign = ctx.dec.bits(1) as i32;
+        }
+        let sign = (1 - 2 * sign) as f32;
+        let (x2, y2) = if itheta > 8192 { (&mut *y, &mut *x) } else { (&mut *x, &mut *y) };
+        cm = quant_band(ctx, x2, n, mbits, bb, lowband, lm, lowband_out, 1.0, orig_fill);
+        y2[0] = -sign * x2[1];
+        y2[1] = sign * x2[0];
+        x[0] *= mid;
+        x[1] *= mid;
+        y[0] *= side;
+        y[1] *= side;
+        for j in 0..2 {
+            let tmp = x[j];
+            x[j] = tmp - y[j];
+            y[j] += tmp;
+        }
+    } else {
+        let mut mbits = b.min((b - split.delta) / 2).max(0);
+        let mut sbits = b - mbits;
+        ctx.remaining_bits -= split.qalloc;
+        let mut rebalance = ctx.remaining_bits;
+        let (x, y) = (&mut x[..n], &mut y[..n]);
+        // The mid is not scaled because it is needed normalized for folding
+        if mbits >= sbits {
+            let mut c = quant_band(ctx, x, n, mbits, bb, lowband, lm, lowband_out, 1.0, fill);
+            rebalance = mbits - (rebalance - ctx.remaining_bits);
+            if rebalance > 3 << BITRES && itheta != 0 {
+                sbits += rebalance - (3 << BITRES);
+            }
+            c |= quant_band(ctx, y, n, sbits, bb, None, lm, None, side, fill >> bb);
+            cm = c;
+        } else {
+            let mut c = quant_band(ctx, y, n, sbits, bb, None, lm, None, side, fill >> bb);
+            rebalance = sbits - (rebalance - ctx.remaining_bits);
+            if rebalance > 3 << BITRES && itheta != 16384 {
+                mbits += rebalance - (3 << BITRES);
+            }
+            c |= quant_band(ctx, x, n, mbits, bb, lowband, lm, lowband_out, 1.0, fill);
+            cm = c;
+        }
+        stereo_merge(x, y, mid);
+    }
+    if split.inv {
+        for v in &mut y[..n] {
+            *v = -*v;
+        }
+    }
+    cm
+}
+
+/// Parameters of [`quant_all_bands`] fixed by the frame header
+pub struct BandParams<'a> {
+    pub start: usize,
+    pub end: usize,
+    pub pulses: &'a [i32; NB_EBANDS],
+    pub short_blocks: bool,
+    pub spread: i32,
+    pub dual_stereo: bool,
+    pub intensity: usize,
+    pub tf_res: &'a [i32; NB_EBANDS],
+    pub total_bits: i32,
+    pub balance: i32,
+    pub lm: usize,
+    pub coded_bands: usize,
+    pub disable_inv: bool,
+}
+
+/// Decode the normalised shapes of all bands into `x` (and `y` for stereo)
+pub fn quant_all_bands(
+    cache: &PulseCache,
+    p: &BandParams,
+    x: &mut [f32],
+    mut y: Option<&mut [f32]>,
+    collapse_masks: &mut [u8],
+    dec: &mut RangeDecoder,
+    seed: &mut u32,
+) {
+    let m = 1usize << p.lm;
+    let c = if y.is_some() { 2 } else { 1 };
+    let bb: i32 = if p.short_blocks { m as i32 } else { 1 };
+    let norm_offset = m * EBANDS[p.start] as usize;
+    // No folding output is kept for the last band
+    let norm_len = m * EBANDS[NB_EBANDS - 1] as usize - norm_offset;
+    let mut norm = vec![0.0f32; norm_len];
+    let mut norm2 = vec![0.0f32; norm_len];
+    let mut dual_stereo = p.dual_stereo;
+    let mut balance = p.balance;
+    let mut lowband_offset = 0;
+    let mut update_lowband = true;
+    let mut ctx = BandCtx {
+        dec,
+        cache,
+        i: 0,
+        intensity: p.intensity,
+        spread: p.spread,
+        tf_change: 0,
+        remaining_bits: 0,
+        seed: *seed,
+        disable_inv: p.disable_inv,
+    };
+    for i in p.start..p.end {
+        let last = i == p.end - 1;
+        let band_start = m * EBANDS[i] as usize;
+        let n = m * EBANDS[i + 1] as usize - band_start;
+        let tell = ctx.dec.tell_frac() as i32;
+        if i != p.start {
+            balance -= tell;
+        }
+        let remaining_bits = p.total_bits - tell - 1;
+        ctx.remaining_bits = remaining_bits;
+        let b = if i < p.coded_bands {
+            let curr_balance = balance / 3.min(p.coded_bands - i) as i32;
+            (remaining_bits + 1).min(p.pulses[i] + curr_balance).clamp(0, 16383)
+        } else {
+            0
+        };
+        if (band_start as isize - n as isize >= norm_offset as isize || i == p.start + 1) && (update_lowband || lowband_offset == 0) {
+            lowband_offset = i;
+        }
+        if i == p.start + 1 {
+            special_hybrid_folding(&mut norm, &mut norm2, p.start, m, dual_stereo);
+        }
+        ctx.i = i;
+        ctx.tf_change = p.tf_res[i];
+
+        // Conservative estimate of the collapse masks of the folding source
+        let mut effective_lowband = None;
+        let (mut x_cm, mut y_cm);
+        if lowband_offset != 0 && (p.spread != SPREAD_AGGRESSIVE || bb > 1 || ctx.tf_change < 0) {
+            let eff = (m * EBANDS[lowband_offset] as usize).saturating_sub(norm_offset + n);
+            effective_lowband = Some(eff);
+            let mut fold_start = lowband_offset;
+            loop {
+                fold_start -= 1;
+                if m * EBANDS[fold_start] as usize <= eff + norm_offset { break; }
+            }
+            let mut fold_end = lowband_offset;
+            while fold_end < i && (m * EBANDS[fold_end] as usize) < eff + norm_offset + n {
+                fold_end += 1;
+            }
+            x_cm = 0;
+            y_cm = 0;
+            for fold_i in fold_start..fold_end.max(fold_start + 1) {
+                x_cm |= collapse_masks[fold_i * c] as u32;
+                y_cm |= collapse_masks[fold_i * c + c - 1] as u32;
+            }
+        } else {
+            x_cm = (1 << bb) - 1;
+            y_cm = x_cm;
+        }
+
+        if dual_stereo && i == p.intensity {
+            // Switch off dual stereo to do intensity
+            dual_stereo = false;
+            for j in 0..band_start - norm_offset {
+                norm[j] = 0.5 * (norm[j] + norm2[j]);
+            }
+        }
+        let xb = &mut x[band_start..band_start + n];
+        let out_at = band_start - norm_offset;
+        // The folding source may run into the output region of the first hybrid band
+        let lowband = effective_lowband.map(|e| norm[e..e + n].to_vec());
+        let lowband = lowband.as_deref();
+        let lowband_out = if last { None } else { Some(&mut norm[out_at..out_at + n]) };
+        if dual_stereo {
+            let yb = &mut y.as_deref_mut().expect("dual stereo needs two channels")[band_start..band_start + n];
+            x_cm = quant_band(&mut ctx, xb, n, b / 2, bb, lowband, p.lm as i32, lowband_out, 1.0, x_cm);
+            let lowband2 = effective_lowband.map(|e| norm2[e..e + n].to_vec());
+            let lowband_out2 = if last { None } else { Some(&mut norm2[out_at..out_at + n]) };
+            y_cm = quant_band(&mut ctx, yb, n, b / 2, bb, lowband2.as_deref(), p.lm as i32, lowband_out2, 1.0, y_cm);
+        } else {
+            x_cm = match y.as_deref_mut() {
+                Some(y) => {
+                    let yb = &mut y[band_start..band_start + n];
+                    quant_band_stereo(&mut ctx, xb, yb, n, b, bb, lowband, p.lm as i32, lowband_out, x_cm | y_cm)
+                }
+                None => quant_band(&mut ctx, xb, n, b, bb, lowband, p.lm as i32, lowband_out, 1.0, x_cm | y_cm),
+            };
+            y_cm = x_cm;
+        }
+        collapse_masks[i * c] = x_cm as u8;
+        collapse_masks[i * c + c - 1] = y_cm as u8;
+        balance += p.pulses[i] + tell;
+        // Update the folding position only as long as we have 1 bit/sample depth
+        update_lowband = b > (n as i32) << BITRES;
+    }
+    *seed = ctx.seed;
+}
+
+/// Duplicate enough of the first hybrid band to fold the second one
+fn special_hybrid_folding(norm: &mut [f32], norm2: &mut [f32], start: usize, m: usize, dual_stereo: bool) {
+    let n1 = m * (EBANDS[start + 1] - EBANDS[start]) as usize;
+    let n2 = m * (EBANDS[start + 2] - EBANDS[start + 1]) as usize;
+    if n2 > n1 {
+        norm.copy_within(2 * n1 - n2..n1, n1);
+        if dual_stereo {
+            norm2.copy_within(2 * n1 - n2..n1, n1);
+        }
+    }
+}
+
+/// Scale the normalised bands back up by their energies
+pub fn denormalise_bands(x: &[f32], freq: &mut [f32], band_log_e: &[f32], start: usize, end: usize, m: usize, silence: bool) {
+    let n = m * SHORT_MDCT_SIZE;
+    let (mut start, mut end, mut bound) = (start, end, m * EBANDS[end] as usize);
+    if silence {
+        bound = 0;
+        start = 0;
+        end = 0;
+    }
+    freq[..m * EBANDS[start] as usize].fill(0.0);
+    for i in start..end {
+        let lg = band_log_e[i] + E_MEANS[i];
+        let g = exp2(lg.min(32.0));
+        for j in m * EBANDS[i] as usize..m * EBANDS[i + 1] as usize {
+            freq[j] = x[j] * g;
+        }
+    }
+    freq[bound..n].fill(0.0);
+}
+
+/// Fill collapsed short blocks of transient frames with noise
+#[allow(clippy::too_many_arguments)]
+pub fn anti_collapse(
+    x: &mut [f32],
+    collapse_masks: &[u8],
+    lm: usize,
+    c: usize,
+    size: usize,
+    start: usize,
+    end: usize,
+    log_e: &[f32],
+    prev1_log_e: &[f32],
+    prev2_log_e: &[f32],
+    pulses: &[i32],
+    mut seed: u32,
+) {
+    for i in start..end {
+        let n0 = (EBANDS[i + 1] - EBANDS[i]) as usize;
+        let depth = ((1 + pulses[i]) as u32 / n0 as u32) as i32 >> lm;
+        let thresh = 0.5 * exp2(-0.125 * depth as f32);
+        let sqrt_1 = 1.0 / ((n0 << lm) as f32).sqrt();
+        for ch in 0..c {
+            let mut prev1 = prev1_log_e[ch * NB_EBANDS + i];
+            let mut prev2 = prev2_log_e[ch * NB_EBANDS + i];
+            if c == 1 {
+                prev1 = prev1.max(prev1_log_e[NB_EBANDS + i]);
+                prev2 = prev2.max(prev2_log_e[NB_EBANDS + i]);
+            }
+            let ediff = (log_e[ch * NB_EBANDS + i] - prev1.min(prev2)).max(0.0);
+            // Short blocks don't have the same energy as long ones
+            let mut r = 2.0 * exp2(-ediff);
+            if lm == 3 {
+                r *= std::f32::consts::SQRT_2;
+            }
+            r = thresh.min(r) * sqrt_1;
+            let band = &mut x[ch * size + ((EBANDS[i] as usize) << lm)..][..n0 << lm];
+            let mut renormalize = false;
+            for k in 0..1 << lm {
+                if collapse_masks[i * c + ch] & 1 << k == 0 {
+                    for j in 0..n0 {
+                        seed = lcg_rand(seed);
+                        band[(j << lm) + k] = if seed & 0x8000 != 0 { r } else { -r };
+                    }
+                    renormalize = true;
+                }
+            }
+            if renormalize {
+                renormalise_vector(band, 1.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pvq_codebook_size() {
+        // V(N, K): signed pulse vectors of dimension N with K unit pulses
+        let mut u = vec![0u32; 5];
+        assert_eq!(ncwrs_urow(2, 2, &mut u), 8);
+        let mut u = vec![0u32; 5];
+        assert_eq!(ncwrs_urow(3, 3, &mut u), 38);
+    }
+}
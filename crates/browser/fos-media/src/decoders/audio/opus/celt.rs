@@ -0,0 +1,681 @@
+//! CELT Decoder
+//!
+//! The MDCT layer of Opus (RFC 6716 section 4.3): band energies, PVQ
+//! shapes, inverse MDCT with low-overlap windows, pitch post-filter and
+//! de-emphasis. Lost frames are concealed with shaped noise.
+
+use std::sync::OnceLock;
+
+use super::bands::{anti_collapse, denormalise_bands, lcg_rand, quant_all_bands, renormalise_vector, BandParams, SPREAD_NORMAL};
+use super::range::{RangeDecoder, BITRES};
+use super::rate::{compute_allocation, PulseCache, EBANDS, MAX_FINE_BITS, MAX_LM, NB_EBANDS, OVERLAP, SHORT_MDCT_SIZE};
+use crate::decoders::audio::mdct::{Complex, Fft};
+
+const DECODE_BUFFER_SIZE: usize = 2048;
+const COMBFILTER_MINPERIOD: usize = 15;
+const PREEMPH: f32 = 0.850_006_1;
+
+const TAPSET_ICDF: [u8; 3] = [2, 1, 0];
+const SPREAD_ICDF: [u8; 4] = [25, 23, 2, 0];
+const TRIM_ICDF: [u8; 11] = [126, 124, 119, 109, 87, 41, 19, 9, 4, 2, 0];
+const SMALL_ENERGY_ICDF: [u8; 3] = [2, 1, 0];
+
+/// Per-band TF resolution changes, indexed by `4*transient + 2*tf_select + flag`
+const TF_SELECT_TABLE: [[i32; 8]; 4] = [
+    [0, -1, 0, -1, 0, -1, 0, -1],
+    [0, -1, 0, -2, 1, 0, 1, -1],
+    [0, -2, 0, -3, 2, 0, 1, -1],
+    [0, -2, 0, -3, 3, 0, 1, -1],
+];
+
+/// Energy prediction coefficients per frame size
+const PRED_COEF: [f32; 4] = [29440.0 / 32768.0, 26112.0 / 32768.0, 21248.0 / 32768.0, 16384.0 / 32768.0];
+const BETA_COEF: [f32; 4] = [30147.0 / 32768.0, 22282.0 / 32768.0, 12124.0 / 32768.0, 6554.0 / 32768.0];
+const BETA_INTRA: f32 = 4915.0 / 32768.0;
+
+/// Laplace parameters (probability of zero, decay) of the coarse energy per
+/// frame size, inter/intra and band
+#[rustfmt::skip]
+const E_PROB_MODEL: [[[u8; 42]; 2]; 4] = [
+    [
+        [72, 127, 65, 129, 66, 128, 65, 128, 64, 128, 62, 128, 64, 128, 64, 128, 92, 78, 92, 79, 92, 78, 90, 79, 116, 41, 115, 40, 114, 40, 132, 26, 132, 26, 145, 17, 161, 12, 176, 10, 177, 11],
+        [24, 179, 48, 138, 54, 135, 54, 132, 53, 134, 56, 133, 55, 132, 55, 132, 61, 114, 70, 96, 74, 88, 75, 88, 87, 74, 89, 66, 91, 67, 100, 59, 108, 50, 120, 40, 122, 37, 97, 43, 78, 50],
+    ],
+    [
+        [83, 78, 84, 81, 88, 75, 86, 74, 87, 71, 90, 73, 93, 74, 93, 74, 109, 40, 114, 36, 117, 34, 117, 34, 143, 17, 145, 18, 146, 19, 162, 12, 165, 10, 178, 7, 189, 6, 190, 8, 177, 9],
+        [23, 178, 54, 115, 63, 102, 66, 98, 69, 99, 74, 89, 71, 91, 73, 91, 78, 89, 86, 80, 92, 66, 93, 64, 102, 59, 103, 60, 104, 60, 117, 52, 123, 44, 138, 35, 133, 31, 97, 38, 77, 45],
+    ],
+    [
+        [61, 90, 93, 60, 105, 42, 107, 41, 110, 45, 116, 38, 113, 38, 112, 38, 124, 26, 132, 27, 136, 19, 140, 20, 155, 14, 159, 16, 158, 18, 170, 13, 177, 10, 187, 8, 192, 6, 175, 9, 159, 10],
+        [21, 178, 59, 110, 71, 86, 75, 85, 84, 83, 91, 66, 88, 73, 87, 72, 92, 75, 98, 72, 105, 58, 107, 54, 115, 52, 114, 55, 112, 56, 129, 51, 132, 40, 150, 33, 140, 29, 98, 35, 77, 42],
+    ],
+    [
+        [42, 121, 96, 66, 108, 43, 111, 40, 117, 44, 123, 32, 120, 36, 119, 33, 127, 33, 134, 34, 139, 21, 147, 23, 152, 20, 158, 25, 154, 26, 166, 21, 173, 16, 184, 13, 184, 10, 150, 13, 139, 15],
+        [22, 178, 63, 114, 74, 82, 84, 83, 92, 82, 103, 62, 96, 72, 96, 67, 101, 73, 107, 72, 113, 55, 118, 52, 125, 52, 118, 52, 117, 55, 135, 49, 137, 39, 157, 32, 145, 29, 97, 33, 77, 40],
+    ],
+];
+
+/// Inverse MDCT of one frame size, windowed for TDAC with the low-overlap window
+struct Mdct {
+    n: usize,
+    fft: Fft,
+    trig: Vec<f32>,
+}
+
+impl Mdct {
+    fn new(n: usize) -> Self {
+        let trig = (0..n / 2).map(|i| (2.0 * std::f64::consts::PI * (i as f64 + 0.125) / n as f64).cos() as f32).collect();
+        Self { n, fft: Fft::new(n / 4), trig }
+    }
+
+    /// Inverse transform of `input[0], input[stride], ...` into
+    /// `out[..OVERLAP / 2 + n / 2]`, overlap-adding the first `OVERLAP` samples
+    fn backward(&self, input: &[f32], stride: usize, out: &mut [f32], window: &[f32; OVERLAP]) {
+        let (n2, n4) = (self.n / 2, self.n / 4);
+        let t = &self.trig;
+        // Pre-rotation, swapping real and imaginary parts to use a forward FFT
+        let rotated: Vec<Complex> = (0..n4)
+            .map(|i| {
+                let x1 = input[2 * i * stride];
+                let x2 = input[stride * (n2 - 1 - 2 * i)];
+                let yr = x2 * t[i] + x1 * t[n4 + i];
+                let yi = x1 * t[i] - x2 * t[n4 + i];
+                Complex::new(yi, yr)
+            })
+            .collect();
+        let mut spectrum = vec![Complex::default(); n4];
+        self.fft.process(&rotated, &mut spectrum);
+        let base = OVERLAP / 2;
+        for (k, v) in spectrum.iter().enumerate() {
+            out[base + 2 * k] = v.re;
+            out[base + 2 * k + 1] = v.im;
+        }
+        // Post-rotate from both ends at once so it can run in place
+        let (mut p0, mut p1) = (base, base + n2 - 2);
+        for i in 0..(n4 + 1) >> 1 {
+            let (re, im) = (out[p0 + 1], out[p0]);
+            let (t0, t1) = (t[i], t[n4 + i]);
+            let yr = re * t0 + im * t1;
+            let yi = re * t1 - im * t0;
+            let (re, im) = (out[p1 + 1], out[p1]);
+            out[p0] = yr;
+            out[p1 + 1] = yi;
+            let (t0, t1) = (t[n4 - i - 1], t[n2 - i - 1]);
+            let yr = re * t0 + im * t1;
+            let yi = re * t1 - im * t0;
+            out[p1] = yr;
+            out[p0 + 1] = yi;
+            p0 += 2;
+            p1 -= 2;
+        }
+        // Mirror on both sides for TDAC
+        for i in 0..OVERLAP / 2 {
+            let (x1, x2) = (out[OVERLAP - 1 - i], out[i]);
+            out[i] = window[OVERLAP - 1 - i] * x2 - window[i] * x1;
+            out[OVERLAP - 1 - i] = window[i] * x2 + window[OVERLAP - 1 - i] * x1;
+        }
+    }
+}
+
+/// Static tables of the 48 kHz mode
+struct Mode {
+    window: [f32; OVERLAP],
+    cache: PulseCache,
+    /// Transforms for 1920 >> shift samples
+    mdct: Vec<Mdct>,
+}
+
+fn mode() -> &'static Mode {
+    static MODE: OnceLock<Mode> = OnceLock::new();
+    MODE.get_or_init(|| {
+        let mut window = [0.0f32; OVERLAP];
+        for (i, w) in window.iter_mut().enumerate() {
+            let s = (0.5 * std::f64::consts::PI * (i as f64 + 0.5) / OVERLAP as f64).sin();
+            *w = (0.5 * std::f64::consts::PI * s * s).sin() as f32;
+        }
+        let mdct = (0..=MAX_LM).map(|shift| Mdct::new((2 * SHORT_MDCT_SIZE) << MAX_LM >> shift)).collect();
+        Mode { window, cache: PulseCache::new(), mdct }
+    })
+}
+
+/// Low-overlap MDCT window, also used for Opus mode transitions
+pub fn window() -> &'static [f32; OVERLAP] { &mode().window }
+
+fn band_width(i: usize) -> i32 { (EBANDS[i + 1] - EBANDS[i]) as i32 }
+
+/// CELT decoder state for one or two output channels
+#[derive(Debug, Clone)]
+pub struct CeltDecoder {
+    channels: usize,
+    stream_channels: usize,
+    start: usize,
+    end: usize,
+    disable_inv: bool,
+    rng: u32,
+    loss_count: u32,
+    postfilter_period: usize,
+    postfilter_period_old: usize,
+    postfilter_gain: f32,
+    postfilter_gain_old: f32,
+    postfilter_tapset: usize,
+    postfilter_tapset_old: usize,
+    preemph_mem: [f32; 2],
+    /// Synthesis history per channel, `DECODE_BUFFER_SIZE + OVERLAP` samples
+    decode_mem: Vec<Vec<f32>>,
+    old_band_e: [f32; 2 * NB_EBANDS],
+    old_log_e: [f32; 2 * NB_EBANDS],
+    old_log_e2: [f32; 2 * NB_EBANDS],
+    background_log_e: [f32; 2 * NB_EBANDS],
+}
+
+impl CeltDecoder {
+    pub fn new(channels: usize) -> Self {
+        let mut decoder = Self {
+            channels,
+            stream_channels: channels,
+            start: 0,
+            end: NB_EBANDS,
+            disable_inv: channels == 1,
+            rng: 0,
+            loss_count: 0,
+            postfilter_period: 0,
+            postfilter_period_old: 0,
+            postfilter_gain: 0.0,
+            postfilter_gain_old: 0.0,
+            postfilter_tapset: 0,
+            postfilter_tapset_old: 0,
+            preemph_mem: [0.0; 2],
+            decode_mem: vec![vec![0.0; DECODE_BUFFER_SIZE + OVERLAP]; channels],
+            old_band_e: [0.0; 2 * NB_EBANDS],
+            old_log_e: [0.0; 2 * NB_EBANDS],
+            old_log_e2: [0.0; 2 * NB_EBANDS],
+            background_log_e: [0.0; 2 * NB_EBANDS],
+        };
+        decoder.reset();
+        decoder
+    }
+
+    pub fn reset(&mut self) {
+        self.rng = 0;
+        self.loss_count = 0;
+        self.postfilter_period = 0;
+        self.postfilter_period_old = 0;
+        self.postfilter_gain = 0.0;
+        self.postfilter_gain_old = 0.0;
+        self.postfilter_tapset = 0;
+        self.postfilter_tapset_old = 0;
+        self.preemph_mem = [0.0; 2];
+        for mem in &mut self.decode_mem {
+            mem.fill(0.0);
+        }
+        self.old_band_e = [0.0; 2 * NB_EBANDS];
+        self.old_log_e = [-28.0; 2 * NB_EBANDS];
+        self.old_log_e2 = [-28.0; 2 * NB_EBANDS];
+        self.background_log_e = [0.0; 2 * NB_EBANDS];
+    }
+
+    /// First coded band (17 in hybrid frames)
+    pub fn set_start_band(&mut self, start: usize) { self.start = start; }
+
+    /// One past the last coded band, set from the Opus bandwidth
+    pub fn set_end_band(&mut self, end: usize) { self.end = end; }
+
+    /// Channels coded in the packet, which may differ from the output
+    pub fn set_stream_channels(&mut self, channels: usize) { self.stream_channels = channels; }
+
+    /// Range coder state after the last decoded frame
+    pub fn final_range(&self) -> u32 { self.rng }
+
+    /// Decode one frame of `frame_size` samples per channel into interleaved
+    /// `pcm`. `len` is the frame size in bytes; `None` conceals a lost frame.
+    pub fn decode(&mut self, dec: Option<&mut RangeDecoder>, len: usize, frame_size: usize, pcm: &mut [f32]) {
+        let lm = (0..=MAX_LM).find(|&lm| SHORT_MDCT_SIZE << lm == frame_size).unwrap_or(MAX_LM);
+        let n = SHORT_MDCT_SIZE << lm;
+        let dec = match dec {
+            Some(dec) if len > 1 => dec,
+            _ => {
+                self.decode_lost(n, lm);
+                self.deemphasis(n, pcm);
+                return;
+            }
+        };
+        self.decode_frame(dec, len, n, lm);
+        self.deemphasis(n, pcm);
+    }
+
+    fn decode_frame(&mut self, dec: &mut RangeDecoder, len: usize, n: usize, lm: usize) {
+        let mode = mode();
+        let (start, end) = (self.start, self.end);
+        let c = self.stream_channels;
+        let m = 1usize << lm;
+
+        if c == 1 {
+            for i in 0..NB_EBANDS {
+                self.old_band_e[i] = self.old_band_e[i].max(self.old_band_e[NB_EBANDS + i]);
+            }
+        }
+
+        let mut total_bits = len as i32 * 8;
+        let mut tell = dec.tell();
+        let silence = if tell >= total_bits {
+            true
+        } else if tell == 1 {
+            dec.bit_logp(15)
+        } else {
+            false
+        };
+        if silence {
+            // Pretend we've read all the remaining bits
+            tell = len as i32 * 8;
+            dec.skip_to_end();
+        }
+
+        let (mut postfilter_pitch, mut postfilter_gain, mut postfilter_tapset) = (0, 0.0, 0);
+        if start == 0 && tell + 16 <= total_bits {
+            if dec.bit_logp(1) {
+                let octave = dec.uint(6);
+                postfilter_pitch = (16 << octave) + dec.bits(4 + octave) as usize - 1;
+                let qg = dec.bits(3);
+                if dec.tell() + 2 <= total_bits {
+                    postfilter_tapset = dec.icdf(&TAPSET_ICDF, 2);
+                }
+                postfilter_gain = 0.09375 * (qg + 1) as f32;
+            }
+            tell = dec.tell();
+        }
+
+        let mut is_transient = false;
+        if lm > 0 && tell + 3 <= total_bits {
+            is_transient = dec.bit_logp(3);
+            tell = dec.tell();
+        }
+        let intra = tell + 3 <= total_bits && dec.bit_logp(3);
+        self.unquant_coarse_energy(dec, intra, c, lm);
+        let tf_res = tf_decode(start, end, is_transient, lm, dec);
+
+        tell = dec.tell();
+        let spread = if tell + 4 <= total_bits { dec.icdf(&SPREAD_ICDF, 5) as i32 } else { SPREAD_NORMAL };
+
+        let cap = mode.cache.init_caps(lm, c);
+        let mut offsets = [0i32; NB_EBANDS];
+        let mut dynalloc_logp = 6;
+        total_bits <<= BITRES;
+        let mut tell = dec.tell_frac() as i32;
+        for i in start..end {
+            let width = (c as i32 * band_width(i)) << lm;
+            // Quanta is 6 bits, but no more than 1 bit/sample and no less than 1/8 bit/sample
+            let quanta = (width << BITRES).min((6 << BITRES).max(width));
+            let mut loop_logp = dynalloc_logp;
+            let mut boost = 0;
+            while tell + (loop_logp << BITRES) < total_bits && boost < cap[i] {
+                let flag = dec.bit_logp(loop_logp as u32);
+                tell = dec.tell_frac() as i32;
+                if !flag {
+                    break;
+                }
+                boost += quanta;
+                total_bits -= quanta;
+                loop_logp = 1;
+            }
+            offsets[i] = boost;
+            // Making dynalloc more likely
+            if boost > 0 {
+                dynalloc_logp = (dynalloc_logp - 1).max(2);
+            }
+        }
+        let alloc_trim = if tell + (6 << BITRES) <= total_bits { dec.icdf(&TRIM_ICDF, 7) as i32 } else { 5 };
+
+        let mut bits = ((len as i32 * 8) << BITRES) - dec.tell_frac() as i32 - 1;
+        let anti_collapse_rsv = if is_transient && lm >= 2 && bits >= (lm as i32 + 2) << BITRES { 1 << BITRES } else { 0 };
+        bits -= anti_collapse_rsv;
+        let alloc = compute_allocation(start, end, &offsets, &cap, alloc_trim, bits, c, lm, dec);
+        self.unquant_fine_energy(dec, &alloc.fine_quant, c);
+
+        for mem in &mut self.decode_mem {
+            mem.copy_within(n..DECODE_BUFFER_SIZE + OVERLAP / 2, 0);
+        }
+
+        let mut x = vec![0.0f32; c * n];
+        let mut collapse_masks = [0u8; 2 * NB_EBANDS];
+        {
+            let (xs, ys) = x.split_at_mut(n);
+            let params = BandParams {
+                start,
+                end,
+                pulses: &alloc.pulses,
+                short_blocks: is_transient,
+                spread,
+                dual_stereo: alloc.dual_stereo,
+                intensity: alloc.intensity,
+                tf_res: &tf_res,
+                total_bits: len as i32 * (8 << BITRES) - anti_collapse_rsv,
+                balance: alloc.balance,
+                lm,
+                coded_bands: alloc.coded_bands,
+                disable_inv: self.disable_inv,
+            };
+            let ys = if c == 2 { Some(ys) } else { None };
+            quant_all_bands(&mode.cache, &params, xs, ys, &mut collapse_masks, dec, &mut self.rng);
+        }
+
+        let anti_collapse_on = anti_collapse_rsv > 0 && dec.bits(1) != 0;
+        let bits_left = len as i32 * 8 - dec.tell();
+        self.unquant_energy_finalise(dec, &alloc.fine_quant, &alloc.fine_priority, bits_left, c);
+        if anti_collapse_on {
+            anti_collapse(
+                &mut x, &collapse_masks, lm, c, n, start, end, &self.old_band_e, &self.old_log_e, &self.old_log_e2, &alloc.pulses, self.rng,
+            );
+        }
+        if silence {
+            self.old_band_e[..c * NB_EBANDS].fill(-28.0);
+        }
+
+        self.synthesis(&x, n, lm, c, start, end, is_transient, silence);
+
+        self.postfilter_period = self.postfilter_period.max(COMBFILTER_MINPERIOD);
+        self.postfilter_period_old = self.postfilter_period_old.max(COMBFILTER_MINPERIOD);
+        let window = &mode.window;
+        for mem in &mut self.decode_mem {
+            let out = DECODE_BUFFER_SIZE - n;
+            comb_filter(
+                mem,
+                out,
+                [self.postfilter_period_old, self.postfilter_period],
+                SHORT_MDCT_SIZE,
+                [self.postfilter_gain_old, self.postfilter_gain],
+                [self.postfilter_tapset_old, self.postfilter_tapset],
+                window,
+            );
+            if lm != 0 {
+                comb_filter(
+                    mem,
+                    out + SHORT_MDCT_SIZE,
+                    [self.postfilter_period, postfilter_pitch],
+                    n - SHORT_MDCT_SIZE,
+                    [self.postfilter_gain, postfilter_gain],
+                    [self.postfilter_tapset, postfilter_tapset],
+                    window,
+                );
+            }
+        }
+        self.postfilter_period_old = self.postfilter_period;
+        self.postfilter_gain_old = self.postfilter_gain;
+        self.postfilter_tapset_old = self.postfilter_tapset;
+        self.postfilter_period = postfilter_pitch;
+        self.postfilter_gain = postfilter_gain;
+        self.postfilter_tapset = postfilter_tapset;
+        if lm != 0 {
+            self.postfilter_period_old = self.postfilter_period;
+            self.postfilter_gain_old = self.postfilter_gain;
+            self.postfilter_tapset_old = self.postfilter_tapset;
+        }
+
+        if c == 1 {
+            self.old_band_e.copy_within(..NB_EBANDS, NB_EBANDS);
+        }
+        if !is_transient {
+            self.old_log_e2 = self.old_log_e;
+            self.old_log_e = self.old_band_e;
+            // The noise floor may rise by 2.4 dB/second, or faster after a long loss
+            let max_background_increase = if self.loss_count < 10 { m as f32 * 0.001 } else { 1.0 };
+            for (bg, &e) in self.background_log_e.iter_mut().zip(&self.old_band_e) {
+                *bg = (*bg + max_background_increase).min(e);
+            }
+        } else {
+            for (old, &e) in self.old_log_e.iter_mut().zip(&self.old_band_e) {
+                *old = old.min(e);
+            }
+        }
+        for ch in 0..2 {
+            for i in (0..start).chain(end..NB_EBANDS) {
+                self.old_band_e[ch * NB_EBANDS + i] = 0.0;
+                self.old_log_e[ch * NB_EBANDS + i] = -28.0;
+                self.old_log_e2[ch * NB_EBANDS + i] = -28.0;
+            }
+        }
+        self.rng = dec.range();
+        self.loss_count = 0;
+    }
+
+    /// Noise-based concealment following the decayed band energies
+    fn decode_lost(&mut self, n: usize, lm: usize) {
+        let c = self.channels;
+        let (start, end) = (self.start, self.end);
+        let decay = if self.loss_count == 0 { 1.5 } else { 0.5 };
+        for ch in 0..c {
+            for i in start..end {
+                let k = ch * NB_EBANDS + i;
+                self.old_band_e[k] = self.background_log_e[k].max(self.old_band_e[k] - decay);
+            }
+        }
+        let mut x = vec![0.0f32; c * n];
+        let mut seed = self.rng;
+        for ch in 0..c {
+            for i in start..end {
+                let band = &mut x[n * ch + ((EBANDS[i] as usize) << lm)..][..(band_width(i) as usize) << lm];
+                for v in band.iter_mut() {
+                    seed = lcg_rand(seed);
+                    *v = (seed as i32 >> 20) as f32;
+                }
+                renormalise_vector(band, 1.0);
+            }
+        }
+        self.rng = seed;
+        for mem in &mut self.decode_mem {
+            mem.copy_within(n..DECODE_BUFFER_SIZE + OVERLAP / 2, 0);
+        }
+        self.synthesis(&x, n, lm, c, start, end, false, false);
+        self.loss_count += 1;
+    }
+
+    /// Inverse MDCT of the denormalised bands into the synthesis history
+    #[allow(clippy::too_many_arguments)]
+    fn synthesis(&mut self, x: &[f32], n: usize, lm: usize, c: usize, start: usize, end: usize, is_transient: bool, silence: bool) {
+        let mode = mode();
+        let m = 1 << lm;
+        let (blocks, nb, shift) = if is_transient { (m, SHORT_MDCT_SIZE, MAX_LM) } else { (1, SHORT_MDCT_SIZE << lm, MAX_LM - lm) };
+        let mdct = &mode.mdct[shift];
+        let out = DECODE_BUFFER_SIZE - n;
+        let mut freq = vec![0.0f32; n];
+        let cc = self.channels;
+        if cc == 1 && c == 2 {
+            // Downmixing a stereo stream to mono
+            let mut freq2 = vec![0.0f32; n];
+            denormalise_bands(&x[..n], &mut freq, &self.old_band_e[..NB_EBANDS], start, end, m, silence);
+            denormalise_bands(&x[n..], &mut freq2, &self.old_band_e[NB_EBANDS..], start, end, m, silence);
+            for (f, &f2) in freq.iter_mut().zip(&freq2) {
+                *f = 0.5 * *f + 0.5 * f2;
+            }
+        } else {
+            denormalise_bands(&x[..n], &mut freq, &self.old_band_e[..NB_EBANDS], start, end, m, silence);
+        }
+        for ch in 0..cc {
+            // A mono stream is copied to both output channels
+            if ch == 1 && c == 2 {
+                denormalise_bands(&x[n..], &mut freq, &self.old_band_e[NB_EBANDS..], start, end, m, silence);
+            }
+            for b in 0..blocks {
+                mdct.backward(&freq[b..], blocks, &mut self.decode_mem[ch][out + nb * b..], &mode.window);
+            }
+        }
+    }
+
+    fn deemphasis(&mut self, n: usize, pcm: &mut [f32]) {
+        let cc = self.channels;
+        for ch in 0..cc {
+            let x = &self.decode_mem[ch][DECODE_BUFFER_SIZE - n..DECODE_BUFFER_SIZE];
+            let mut m = self.preemph_mem[ch];
+            for (j, &v) in x.iter().enumerate() {
+                let tmp = v + 1e-30 + m;
+                m = PREEMPH * tmp;
+                pcm[j * cc + ch] = tmp * (1.0 / 32768.0);
+            }
+            self.preemph_mem[ch] = m;
+        }
+    }
+
+    fn unquant_coarse_energy(&mut self, dec: &mut RangeDecoder, intra: bool, c: usize, lm: usize) {
+        let prob_model = &E_PROB_MODEL[lm][intra as usize];
+        let mut prev = [0.0f32; 2];
+        let (coef, beta) = if intra { (0.0, BETA_INTRA) } else { (PRED_COEF[lm], BETA_COEF[lm]) };
+        let budget = dec.storage() as i32 * 8;
+        for i in self.start..self.end {
+            for (ch, prev) in prev.iter_mut().enumerate().take(c) {
+                let tell = dec.tell();
+                let qi = if budget - tell >= 15 {
+                    let pi = 2 * i.min(20);
+                    dec.laplace((prob_model[pi] as u32) << 7, (prob_model[pi + 1] as u32) << 6)
+                } else if budget - tell >= 2 {
+                    let qi = dec.icdf(&SMALL_ENERGY_ICDF, 2) as i32;
+                    (qi >> 1) ^ -(qi & 1)
+                } else if budget - tell >= 1 {
+                    -(dec.bit_logp(1) as i32)
+                } else {
+                    -1
+                };
+                let q = qi as f32;
+                let old = &mut self.old_band_e[i + ch * NB_EBANDS];
+                *old = old.max(-9.0);
+                *old = coef * *old + *prev + q;
+                *prev = *prev + q - beta * q;
+            }
+        }
+    }
+
+    fn unquant_fine_energy(&mut self, dec: &mut RangeDecoder, fine_quant: &[i32; NB_EBANDS], c: usize) {
+        for (i, &fine) in fine_quant.iter().enumerate().take(self.end).skip(self.start) {
+            if fine <= 0 {
+                continue;
+            }
+            for ch in 0..c {
+                let q2 = dec.bits(fine_quant[i] as u32);
+                let offset = (q2 as f32 + 0.5) * (1 << (14 - fine_quant[i])) as f32 * (1.0 / 16384.0) - 0.5;
+                self.old_band_e[i + ch * NB_EBANDS] += offset;
+            }
+        }
+    }
+
+    fn unquant_energy_finalise(
+        &mut self,
+        dec: &mut RangeDecoder,
+        fine_quant: &[i32; NB_EBANDS],
+        fine_priority: &[i32; NB_EBANDS],
+        mut bits_left: i32,
+        c: usize,
+    ) {
+        // Use up the remaining bits
+        for prio in 0..2 {
+            for i in self.start..self.end {
+                if bits_left < c as i32 {
+                    break;
+                }
+                if fine_quant[i] >= MAX_FINE_BITS || fine_priority[i] != prio {
+                    continue;
+                }
+                for ch in 0..c {
+                    let q2 = dec.bits(1);
+                    let offset = (q2 as f32 - 0.5) * (1 << (14 - fine_quant[i] - 1)) as f32 * (1.0 / 16384.0);
+                    self.old_band_e[i + ch * NB_EBANDS] += offset;
+                    bits_left -= 1;
+                }
+            }
+        }
+    }
+}
+
+/// Per-band time/frequency resolution changes
+fn tf_decode(start: usize, end: usize, is_transient: bool, lm: usize, dec: &mut RangeDecoder) -> [i32; NB_EBANDS] {
+    let mut tf_res = [0i32; NB_EBANDS];
+    let mut budget = dec.storage() as u32 * 8;
+    let mut tell = dec.tell() as u32;
+    let mut logp = if is_transient { 2 } else { 4 };
+    let tf_select_rsv = lm > 0 && tell + logp < budget;
+    budget -= tf_select_rsv as u32;
+    let (mut tf_changed, mut curr) = (0, 0);
+    for res in &mut tf_res[start..end] {
+        if tell + logp <= budget {
+            curr ^= dec.bit_logp(logp) as i32;
+            tell = dec.tell() as u32;
+            tf_changed |= curr;
+        }
+        *res = curr;
+        logp = if is_transient { 4 } else { 5 };
+    }
+    let row = &TF_SELECT_TABLE[lm];
+    let t = 4 * is_transient as usize;
+    let mut tf_select = 0;
+    if tf_select_rsv && row[t + tf_changed as usize] != row[t + 2 + tf_changed as usize] {
+        tf_select = dec.bit_logp(1) as usize;
+    }
+    for res in &mut tf_res[start..end] {
+        *res = row[t + 2 * tf_select + *res as usize];
+    }
+    tf_res
+}
+
+/// Pitch comb filter over `buf[off..off + n]` in place, cross-fading from
+/// the old (index 0) to the new (index 1) parameters over the overlap
+fn comb_filter(buf: &mut [f32], off: usize, period: [usize; 2], n: usize, gain: [f32; 2], tapset: [usize; 2], window: &[f32; OVERLAP]) {
+    #[allow(clippy::excessive_precision)]
+    const GAINS: [[f32; 3]; 3] = [[0.3066406250, 0.2170410156, 0.1296386719], [0.4638671875, 0.2680664062, 0.0], [0.7998046875, 0.1000976562, 0.0]];
+    let [g0, g1] = gain;
+    if g0 == 0.0 && g1 == 0.0 {
+        return;
+    }
+    // With zero gain the period may be unset; keep it long enough to stay in the history
+    let t0 = period[0].max(COMBFILTER_MINPERIOD);
+    let t1 = period[1].max(COMBFILTER_MINPERIOD);
+    let (g00, g01, g02) = (g0 * GAINS[tapset[0]][0], g0 * GAINS[tapset[0]][1], g0 * GAINS[tapset[0]][2]);
+    let (g10, g11, g12) = (g1 * GAINS[tapset[1]][0], g1 * GAINS[tapset[1]][1], g1 * GAINS[tapset[1]][2]);
+    let mut x1 = buf[off - t1 + 1];
+    let mut x2 = buf[off - t1];
+    let mut x3 = buf[off - t1 - 1];
+    let mut x4 = buf[off - t1 - 2];
+    // If the filter didn't change, we don't need the overlap
+    let overlap = if g0 == g1 && t0 == t1 && tapset[0] == tapset[1] { 0 } else { OVERLAP.min(n) };
+    for (i, &w) in window.iter().enumerate().take(overlap) {
+        let k = off + i;
+        let x0 = buf[k - t1 + 2];
+        let f = w * w;
+        buf[k] = buf[k]
+            + (1.0 - f) * g00 * buf[k - t0]
+            + (1.0 - f) * g01 * (buf[k - t0 + 1] + buf[k - t0 - 1])
+            + (1.0 - f) * g02 * (buf[k - t0 + 2] + buf[k - t0 - 2])
+            + f * g10 * x2
+            + f * g11 * (x1 + x3)
+            + f * g12 * (x0 + x4);
+        x4 = x3;
+        x3 = x2;
+        x2 = x1;
+        x1 = x0;
+    }
+    if g1 == 0.0 {
+        return;
+    }
+    // Constant filter for the rest of the frame
+    for i in overlap..n {
+        let k = off + i;
+        let x0 = buf[k - t1 + 2];
+        buf[k] = buf[k] + g10 * x2 + g11 * (x1 + x3) + g12 * (x0 + x4);
+        x4 = x3;
+        x3 = x2;
+        x2 = x1;
+        x1 = x0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silent_frame_decodes_to_silence() {
+        // A frame whose first symbol is the silence flag
+        let data = [0xff, 0xff];
+        let mut decoder = CeltDecoder::new(1);
+        let mut pcm = vec![1.0f32; 960];
+        let mut dec = RangeDecoder::new(&data);
+        decoder.decode(Some(&mut dec), data.len(), 960, &mut pcm);
+        assert!(pcm.iter().all(|v| v.abs() < 1e-6));
+    }
+}
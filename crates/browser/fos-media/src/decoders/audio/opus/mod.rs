@@ -0,0 +1,721 @@
+//! Opus Decoder
+//!
+//! RFC 6716 decoder: TOC parsing and frame packing, SILK (speech), CELT
+//! (music) and hybrid frames with their mode transitions, and the OpusHead
+//! channel mapping for multistream (surround) packets. Output is always
+//! 48 kHz.
+
+mod bands;
+mod celt;
+mod range;
+mod rate;
+mod silk;
+
+use std::time::Duration;
+
+use self::celt::CeltDecoder;
+use self::range::RangeDecoder;
+use self::silk::{SilkConfig, SilkDecoder};
+use super::{AudioDecoderState, AudioDecoderTrait, AudioSamples, DecoderError, DecoderResult, EncodedPacket};
+
+/// Opus always decodes at 48 kHz
+pub const SAMPLE_RATE: u32 = 48000;
+
+/// Longest packet duration (120 ms)
+const MAX_PACKET_SAMPLES: usize = 5760;
+const F20: usize = 960;
+const F10: usize = 480;
+const F5: usize = 240;
+const F2_5: usize = 120;
+
+/// Coding mode of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    SilkOnly,
+    Hybrid,
+    CeltOnly,
+}
+
+/// Audio bandwidth of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bandwidth {
+    Narrow,
+    Medium,
+    Wide,
+    SuperWide,
+    Full,
+}
+
+/// Table-of-contents byte
+#[derive(Debug, Clone, Copy)]
+struct Toc {
+    mode: Mode,
+    bandwidth: Bandwidth,
+    /// Samples per frame at 48 kHz
+    frame_size: usize,
+    stereo: bool,
+}
+
+impl Toc {
+    fn parse(toc: u8) -> Self {
+        let config = (toc >> 3) as usize;
+        let (mode, bandwidth, frame_size) = if toc & 0x80 != 0 {
+            let bandwidth = match (toc >> 5) & 3 {
+                0 => Bandwidth::Narrow,
+                1 => Bandwidth::Wide,
+                2 => Bandwidth::SuperWide,
+                _ => Bandwidth::Full,
+            };
+            (Mode::CeltOnly, bandwidth, F2_5 << (config & 3))
+        } else if toc & 0x60 == 0x60 {
+            let bandwidth = if toc & 0x10 != 0 { Bandwidth::Full } else { Bandwidth::SuperWide };
+            (Mode::Hybrid, bandwidth, if toc & 0x08 != 0 { F20 } else { F10 })
+        } else {
+            let bandwidth = match (toc >> 5) & 3 {
+                0 => Bandwidth::Narrow,
+                1 => Bandwidth::Medium,
+                _ => Bandwidth::Wide,
+            };
+            let frame_size = if config & 3 == 3 { 2880 } else { F10 << (config & 3) };
+            (Mode::SilkOnly, bandwidth, frame_size)
+        };
+        Self { mode, bandwidth, frame_size, stereo: toc & 0x04 != 0 }
+    }
+}
+
+fn invalid_packet() -> DecoderError { DecoderError::InvalidBitstream("Invalid Opus packet".into()) }
+
+/// One or two byte frame length
+fn parse_size(data: &[u8]) -> Option<(usize, usize)> {
+    match *data {
+        [b0, ..] if b0 < 252 => Some((b0 as usize, 1)),
+        [b0, b1, ..] => Some((4 * b1 as usize + b0 as usize, 2)),
+        _ => None,
+    }
+}
+
+/// Split a packet into its frames (RFC 6716 section 3.2). Self-delimited
+/// packets, used by all but the last stream of a multistream packet, carry
+/// an extra length. Returns the TOC, the frames and the bytes consumed.
+fn parse_packet(data: &[u8], self_delimited: bool) -> DecoderResult<(Toc, Vec<&[u8]>, usize)> {
+    let (&toc_byte, mut rest) = data.split_first().ok_or_else(invalid_packet)?;
+    let toc = Toc::parse(toc_byte);
+    let mut sizes = Vec::new();
+    let mut last_size = rest.len();
+    let mut pad = 0;
+    let mut cbr = false;
+    let count = match toc_byte & 3 {
+        0 => 1,
+        1 => {
+            cbr = true;
+            if !self_delimited {
+                if rest.len() % 2 != 0 {
+                    return Err(invalid_packet());
+                }
+                last_size = rest.len() / 2;
+                sizes.push(last_size);
+            }
+            2
+        }
+        2 => {
+            let (size, bytes) = parse_size(rest).ok_or_else(invalid_packet)?;
+            rest = &rest[bytes..];
+            if size > rest.len() {
+                return Err(invalid_packet());
+            }
+            sizes.push(size);
+            last_size = rest.len() - size;
+            2
+        }
+        _ => {
+            let (&ch, tail) = rest.split_first().ok_or_else(invalid_packet)?;
+            rest = tail;
+            let count = (ch & 0x3f) as usize;
+            if count == 0 || toc.frame_size * count > MAX_PACKET_SAMPLES {
+                return Err(invalid_packet());
+            }
+            // Padding flag is bit 6
+            if ch & 0x40 != 0 {
+                loop {
+                    let (&p, tail) = rest.split_first().ok_or_else(invalid_packet)?;
+                    rest = tail;
+                    let tmp = if p == 255 { 254 } else { p as usize };
+                    pad += tmp;
+                    if p != 255 {
+                        break;
+                    }
+                }
+                if pad > rest.len() {
+                    return Err(invalid_packet());
+                }
+                rest = &rest[..rest.len() - pad];
+            }
+            // VBR flag is bit 7
+            cbr = ch & 0x80 == 0;
+            if !cbr {
+                last_size = rest.len();
+                for _ in 0..count - 1 {
+                    let (size, bytes) = parse_size(rest).ok_or_else(invalid_packet)?;
+                    rest = &rest[bytes..];
+                    if size > rest.len() {
+                        return Err(invalid_packet());
+                    }
+                    sizes.push(size);
+                    last_size = last_size.checked_sub(bytes + size).ok_or_else(invalid_packet)?;
+                }
+            } else if !self_delimited {
+                last_size = rest.len() / count;
+                if last_size * count != rest.len() {
+                    return Err(invalid_packet());
+                }
+                sizes.resize(count - 1, last_size);
+            }
+            count
+        }
+    };
+    if self_delimited {
+        let (size, bytes) = parse_size(rest).ok_or_else(invalid_packet)?;
+        rest = &rest[bytes..];
+        if size > rest.len() {
+            return Err(invalid_packet());
+        }
+        if cbr {
+            if size * count > rest.len() {
+                return Err(invalid_packet());
+            }
+            sizes = vec![size; count - 1];
+        } else if bytes + size > last_size {
+            return Err(invalid_packet());
+        }
+        sizes.push(size);
+    } else {
+        // The implicit last size may exceed the maximum frame size
+        if last_size > 1275 {
+            return Err(invalid_packet());
+        }
+        sizes.push(last_size);
+    }
+    let header = data.len() - pad - rest.len();
+    let mut frames = Vec::with_capacity(count);
+    let mut offset = 0;
+    for size in sizes {
+        frames.push(rest.get(offset..offset + size).ok_or_else(invalid_packet)?);
+        offset += size;
+    }
+    Ok((toc, frames, header + offset + pad))
+}
+
+/// Cross-fade from `in1` to `in2` over `n` samples with the squared CELT window
+fn smooth_fade(in1: &[f32], in2: &[f32], out: &mut [f32], n: usize, channels: usize) {
+    let window = celt::window();
+    for c in 0..channels {
+        for (i, &w) in window.iter().enumerate().take(n) {
+            let w = w * w;
+            let k = i * channels + c;
+            out[k] = w * in2[k] + (1.0 - w) * in1[k];
+        }
+    }
+}
+
+/// Decoder for one (mono or coupled stereo) Opus stream
+#[derive(Debug, Clone)]
+struct StreamDecoder {
+    channels: usize,
+    celt: CeltDecoder,
+    silk: SilkDecoder,
+    silk_config: SilkConfig,
+    mode: Mode,
+    bandwidth: Bandwidth,
+    frame_size: usize,
+    stream_channels: usize,
+    /// Mode of the last decoded frame, `None` before the first packet
+    prev_mode: Option<Mode>,
+    prev_redundancy: bool,
+    final_range: u32,
+}
+
+impl StreamDecoder {
+    fn new(channels: usize) -> Self {
+        Self {
+            channels,
+            celt: CeltDecoder::new(channels),
+            silk: SilkDecoder::new(),
+            silk_config: SilkConfig { channels, stream_channels: channels, internal_rate: 16000, payload_ms: 20 },
+            mode: Mode::CeltOnly,
+            bandwidth: Bandwidth::Full,
+            frame_size: F20,
+            stream_channels: channels,
+            prev_mode: None,
+            prev_redundancy: false,
+            final_range: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.celt.reset();
+        self.silk.reset();
+        self.stream_channels = self.channels;
+        self.frame_size = F20;
+        self.prev_mode = None;
+        self.prev_redundancy = false;
+        self.final_range = 0;
+    }
+
+    /// Decode a packet into `pcm`; returns the samples per channel and the
+    /// bytes of `data` it occupied
+    fn decode_packet(&mut self, data: &[u8], self_delimited: bool, pcm: &mut Vec<f32>) -> DecoderResult<(usize, usize)> {
+        let (toc, frames, consumed) = parse_packet(data, self_delimited)?;
+        self.mode = toc.mode;
+        self.bandwidth = toc.bandwidth;
+        self.frame_size = toc.frame_size;
+        self.stream_channels = if toc.stereo { 2 } else { 1 };
+        let total = frames.len() * toc.frame_size;
+        pcm.clear();
+        pcm.resize(total * self.channels, 0.0);
+        let mut samples = 0;
+        for frame in frames {
+            samples += self.decode_frame(Some(frame), total - samples, &mut pcm[samples * self.channels..])?;
+        }
+        Ok((samples, consumed))
+    }
+
+    /// Conceal `frame_size` lost samples per channel
+    fn conceal(&mut self, frame_size: usize, pcm: &mut Vec<f32>) -> DecoderResult<usize> {
+        pcm.clear();
+        pcm.resize(frame_size * self.channels, 0.0);
+        let mut samples = 0;
+        while samples < frame_size {
+            samples += self.decode_frame(None, frame_size - samples, &mut pcm[samples * self.channels..])?;
+        }
+        Ok(samples)
+    }
+
+    /// Decode (or conceal, with `None`) one frame; mirrors the mode switching
+    /// and redundancy handling of the reference decoder
+    fn decode_frame(&mut self, data: Option<&[u8]>, frame_size: usize, pcm: &mut [f32]) -> DecoderResult<usize> {
+        let ch = self.channels;
+        let mut frame_size = frame_size.min(3 * F20);
+        // Payloads of 0 or 1 bytes trigger the concealment
+        let data = data.filter(|d| d.len() > 1);
+        let (mut audiosize, mode, bandwidth);
+        match data {
+            Some(_) => {
+                audiosize = self.frame_size;
+                mode = self.mode;
+                bandwidth = Some(self.bandwidth);
+            }
+            None => {
+                // Don't conceal more than what the TOC says
+                frame_size = frame_size.min(self.frame_size);
+                audiosize = frame_size;
+                bandwidth = None;
+                let Some(prev_mode) = self.prev_mode else {
+                    pcm[..audiosize * ch].fill(0.0);
+                    return Ok(audiosize);
+                };
+                mode = prev_mode;
+                // Conceal in 2.5, 5, 10 or 20 ms pieces
+                if audiosize > F20 {
+                    let mut done = 0;
+                    while done < audiosize {
+                        done += self.decode_frame(None, (audiosize - done).min(F20), &mut pcm[done * ch..])?;
+                    }
+                    return Ok(frame_size);
+                } else if audiosize < F20 {
+                    if audiosize > F10 {
+                        audiosize = F10;
+                    } else if mode != Mode::SilkOnly && audiosize > F5 && audiosize < F10 {
+                        audiosize = F5;
+                    }
+                }
+            }
+        }
+        let mut len = data.map_or(0, |d| d.len());
+        let bytes = data.unwrap_or(&[]);
+        let mut dec = RangeDecoder::new(bytes);
+
+        let mut transition = data.is_some()
+            && self.prev_mode.is_some_and(|prev| {
+                (mode == Mode::CeltOnly && prev != Mode::CeltOnly && !self.prev_redundancy) || (mode != Mode::CeltOnly && prev == Mode::CeltOnly)
+            });
+        let mut pcm_transition = Vec::new();
+        if transition && mode == Mode::CeltOnly {
+            pcm_transition = vec![0.0; F5 * ch];
+            self.decode_frame(None, F5.min(audiosize), &mut pcm_transition)?;
+        }
+        if audiosize > frame_size {
+            return Err(DecoderError::Internal("Opus output buffer too small".into()));
+        }
+        frame_size = audiosize;
+
+        let mut pcm_silk = Vec::new();
+        if mode != Mode::CeltOnly {
+            pcm_silk = vec![0i16; F10.max(frame_size) * ch];
+            if self.prev_mode == Some(Mode::CeltOnly) {
+                self.silk.reset();
+            }
+            // The SILK concealment cannot produce frames of less than 10 ms
+            self.silk_config.payload_ms = (audiosize / 48).max(10);
+            if data.is_some() {
+                self.silk_config.stream_channels = self.stream_channels;
+                self.silk_config.internal_rate = match (mode, self.bandwidth) {
+                    (Mode::SilkOnly, Bandwidth::Narrow) => 8000,
+                    (Mode::SilkOnly, Bandwidth::Medium) => 12000,
+                    _ => 16000,
+                };
+            }
+            let mut decoded = 0;
+            while decoded < frame_size {
+                let config = self.silk_config;
+                decoded += self.silk.decode(&mut dec, data.is_none(), decoded == 0, &config, &mut pcm_silk[decoded * ch..]);
+            }
+        }
+
+        let mut start_band = 0;
+        let mut redundancy = false;
+        let mut celt_to_silk = false;
+        let mut redundancy_bytes = 0;
+        let hybrid_bits = if mode == Mode::Hybrid { 20 } else { 0 };
+        if mode != Mode::CeltOnly && data.is_some() && dec.tell() + 17 + hybrid_bits <= 8 * len as i32 {
+            // Check if we have a redundant 0-8 kHz band
+            redundancy = mode != Mode::Hybrid || dec.bit_logp(12);
+            if redundancy {
+                celt_to_silk = dec.bit_logp(1);
+                redundancy_bytes = if mode == Mode::Hybrid { dec.uint(256) as usize + 2 } else { len - ((dec.tell() as usize + 7) >> 3) };
+                // Never true for a valid packet
+                if redundancy_bytes > len || (len - redundancy_bytes) * 8 < dec.tell() as usize {
+                    len = 0;
+                    redundancy_bytes = 0;
+                    redundancy = false;
+                } else {
+                    len -= redundancy_bytes;
+                }
+                // Raw bits are read from the end of the shortened frame
+                dec.shrink(dec.storage() - redundancy_bytes);
+            }
+        }
+        if mode != Mode::CeltOnly {
+            start_band = 17;
+        }
+        if redundancy {
+            transition = false;
+        }
+        if transition && mode != Mode::CeltOnly {
+            pcm_transition = vec![0.0; F5 * ch];
+            self.decode_frame(None, F5.min(audiosize), &mut pcm_transition)?;
+        }
+
+        if let Some(bandwidth) = bandwidth {
+            self.celt.set_end_band(match bandwidth {
+                Bandwidth::Narrow => 13,
+                Bandwidth::Medium | Bandwidth::Wide => 17,
+                Bandwidth::SuperWide => 19,
+                Bandwidth::Full => 21,
+            });
+        }
+        self.celt.set_stream_channels(self.stream_channels);
+
+        let redundant = &bytes[len.min(bytes.len())..(len + redundancy_bytes).min(bytes.len())];
+        let mut redundant_audio = vec![0.0f32; if redundancy { F5 * ch } else { 0 }];
+        let mut redundant_rng = 0;
+        // 5 ms redundant frame for CELT to SILK
+        if redundancy && celt_to_silk {
+            self.celt.set_start_band(0);
+            let mut rdec = RangeDecoder::new(redundant);
+            self.celt.decode(Some(&mut rdec), redundancy_bytes, F5, &mut redundant_audio);
+            redundant_rng = self.celt.final_range();
+        }
+
+        // Must come after the concealment above
+        self.celt.set_start_band(start_band);
+        if mode != Mode::SilkOnly {
+            let celt_frame_size = F20.min(frame_size);
+            // Discard any previous CELT state
+            if self.prev_mode.is_some_and(|prev| prev != mode) && !self.prev_redundancy {
+                self.celt.reset();
+            }
+            let dec = if data.is_some() { Some(&mut dec) } else { None };
+            self.celt.decode(dec, len, celt_frame_size, pcm);
+        } else {
+            pcm[..frame_size * ch].fill(0.0);
+            // For hybrid to SILK transitions, let the CELT MDCT fade out by decoding a silence frame
+            if self.prev_mode == Some(Mode::Hybrid) && !(redundancy && celt_to_silk && self.prev_redundancy) {
+                self.celt.set_start_band(0);
+                let silence = [0xff, 0xff];
+                let mut sdec = RangeDecoder::new(&silence);
+                self.celt.decode(Some(&mut sdec), 2, F2_5, pcm);
+            }
+        }
+
+        if mode != Mode::CeltOnly {
+            for (p, &s) in pcm[..frame_size * ch].iter_mut().zip(&pcm_silk) {
+                *p += (1.0 / 32768.0) * s as f32;
+            }
+        }
+
+        // 5 ms redundant frame for SILK to CELT
+        if redundancy && !celt_to_silk {
+            self.celt.reset();
+            self.celt.set_start_band(0);
+            let mut rdec = RangeDecoder::new(redundant);
+            self.celt.decode(Some(&mut rdec), redundancy_bytes, F5, &mut redundant_audio);
+            redundant_rng = self.celt.final_range();
+            let at = ch * (frame_size - F2_5);
+            let tail = pcm[at..at + ch * F2_5].to_vec();
+            smooth_fade(&tail, &redundant_audio[ch * F2_5..], &mut pcm[at..], F2_5, ch);
+        }
+        if redundancy && celt_to_silk {
+            pcm[..ch * F2_5].copy_from_slice(&redundant_audio[..ch * F2_5]);
+            let head = pcm[ch * F2_5..ch * F5].to_vec();
+            smooth_fade(&redundant_audio[ch * F2_5..], &head, &mut pcm[ch * F2_5..], F2_5, ch);
+        }
+        if transition {
+            if audiosize >= F5 {
+                pcm[..ch * F2_5].copy_from_slice(&pcm_transition[..ch * F2_5]);
+                let head = pcm[ch * F2_5..ch * F5].to_vec();
+                smooth_fade(&pcm_transition[ch * F2_5..], &head, &mut pcm[ch * F2_5..], F2_5, ch);
+            } else {
+                // Not enough time for a clean transition
+                let head = pcm[..ch * F2_5].to_vec();
+                smooth_fade(&pcm_transition, &head, pcm, F2_5, ch);
+            }
+        }
+
+        self.final_range = if len <= 1 { 0 } else { dec.range() ^ redundant_rng };
+        self.prev_mode = Some(mode);
+        self.prev_redundancy = redundancy && !celt_to_silk;
+        Ok(audiosize)
+    }
+}
+
+/// Identification header (RFC 7845 section 5.1)
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpusHead {
+    pub channels: u8,
+    /// Samples at 48 kHz to discard from the start of the stream
+    pub pre_skip: u16,
+    pub input_sample_rate: u32,
+    /// Gain in Q7.8 dB applied to the output
+    pub output_gain: i16,
+    pub mapping_family: u8,
+    pub stream_count: u8,
+    pub coupled_count: u8,
+    /// Decoded channel for each output channel; 255 is silent
+    pub mapping: Vec<u8>,
+}
+
+impl OpusHead {
+    pub fn parse(data: &[u8]) -> DecoderResult<Self> {
+        let err = || DecoderError::InvalidBitstream("Invalid OpusHead".into());
+        if data.len() < 19 || &data[..8] != b"OpusHead" || data[8] >> 4 != 0 {
+            return Err(err());
+        }
+        let channels = data[9];
+        let mapping_family = data[18];
+        let (stream_count, coupled_count, mapping) = if mapping_family == 0 {
+            if !(1..=2).contains(&channels) {
+                return Err(err());
+            }
+            (1, channels - 1, (0..channels).collect())
+        } else {
+            let table = data.get(19..21 + channels as usize).ok_or_else(err)?;
+            (table[0], table[1], table[2..].to_vec())
+        };
+        let decoded = stream_count as usize + coupled_count as usize;
+        if channels == 0 || stream_count == 0 || coupled_count > stream_count || decoded > 255 {
+            return Err(err());
+        }
+        if mapping.iter().any(|&m| m != 255 && m as usize >= decoded) {
+            return Err(err());
+        }
+        Ok(Self {
+            channels,
+            pre_skip: u16::from_le_bytes([data[10], data[11]]),
+            input_sample_rate: u32::from_le_bytes([data[12], data[13], data[14], data[15]]),
+            output_gain: i16::from_le_bytes([data[16], data[17]]),
+            mapping_family,
+            stream_count,
+            coupled_count,
+            mapping,
+        })
+    }
+
+    /// Plain mono or stereo stream without a header
+    pub fn with_channels(channels: u8) -> Self {
+        Self {
+            channels,
+            pre_skip: 0,
+            input_sample_rate: SAMPLE_RATE,
+            output_gain: 0,
+            mapping_family: 0,
+            stream_count: 1,
+            coupled_count: channels.saturating_sub(1),
+            mapping: (0..channels).collect(),
+        }
+    }
+}
+
+/// Opus Decoder
+#[derive(Debug)]
+pub struct OpusDecoder {
+    state: AudioDecoderState,
+    head: OpusHead,
+    streams: Vec<StreamDecoder>,
+    /// Linear output gain from the header
+    gain: f32,
+    /// Samples per channel still to drop for the header's pre-skip
+    skip: usize,
+    /// Samples per channel of the last packet, the length of a concealed one
+    last_packet_samples: usize,
+}
+
+impl OpusDecoder {
+    pub fn new() -> Self { Self::with_head(OpusHead::with_channels(2)) }
+
+    pub fn with_head(head: OpusHead) -> Self {
+        let streams = (0..head.stream_count).map(|s| StreamDecoder::new(if s < head.coupled_count { 2 } else { 1 })).collect();
+        Self {
+            state: AudioDecoderState { sample_rate: SAMPLE_RATE, channels: head.channels as u32, samples_decoded: 0 },
+            gain: 10f32.powf(head.output_gain as f32 / (20.0 * 256.0)),
+            skip: head.pre_skip as usize,
+            head,
+            streams,
+            last_packet_samples: F20,
+        }
+    }
+
+    pub fn head(&self) -> &OpusHead { &self.head }
+
+    /// Range coder state after the last packet, XORed over the streams
+    pub fn final_range(&self) -> u32 { self.streams.iter().fold(0, |acc, s| acc ^ s.final_range) }
+
+    /// Decode a packet, or conceal a lost one with `None`, into interleaved
+    /// samples of all output channels; returns the samples per channel
+    pub fn decode_to(&mut self, packet: Option<&[u8]>, out: &mut Vec<f32>) -> DecoderResult<usize> {
+        let mut decoded = Vec::with_capacity(self.streams.len());
+        let mut offset = 0;
+        let mut samples = None;
+        let last = self.streams.len() - 1;
+        for (s, stream) in self.streams.iter_mut().enumerate() {
+            let mut pcm = Vec::new();
+            let n = match packet {
+                Some(data) => {
+                    let (n, used) = stream.decode_packet(data.get(offset..).ok_or_else(invalid_packet)?, s != last, &mut pcm)?;
+                    offset += used;
+                    n
+                }
+                None => stream.conceal(self.last_packet_samples, &mut pcm)?,
+            };
+            if samples.is_some_and(|samples| samples != n) {
+                return Err(invalid_packet());
+            }
+            samples = Some(n);
+            decoded.push(pcm);
+        }
+        let samples = samples.unwrap_or(0);
+        self.last_packet_samples = samples;
+        let channels = self.head.channels as usize;
+        let coupled = self.head.coupled_count as usize;
+        out.clear();
+        out.resize(samples * channels, 0.0);
+        for (c, &m) in self.head.mapping.iter().enumerate() {
+            if m == 255 {
+                continue;
+            }
+            let m = m as usize;
+            let (stream, src_channel, stride) = if m < 2 * coupled { (m / 2, m % 2, 2) } else { (m - coupled, 0, 1) };
+            let pcm = &decoded[stream];
+            for i in 0..samples {
+                out[i * channels + c] = pcm[i * stride + src_channel] * self.gain;
+            }
+        }
+        Ok(samples)
+    }
+
+    fn decode_audio_packet(&mut self, data: &[u8], pts: Duration) -> DecoderResult<AudioSamples> {
+        let mut out = Vec::new();
+        let mut frames = self.decode_to(Some(data), &mut out)?;
+        let channels = self.head.channels as usize;
+        if self.skip > 0 {
+            let skip = self.skip.min(frames);
+            out.drain(..skip * channels);
+            frames -= skip;
+            self.skip -= skip;
+        }
+        self.state.samples_decoded += frames as u64;
+        let duration = Duration::from_secs_f64(frames as f64 / SAMPLE_RATE as f64);
+        Ok(AudioSamples { pts, duration, sample_rate: SAMPLE_RATE, channels: channels as u32, data: out })
+    }
+}
+
+impl Default for OpusDecoder { fn default() -> Self { Self::new() } }
+
+impl AudioDecoderTrait for OpusDecoder {
+    fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<AudioSamples> {
+        if packet.data.starts_with(b"OpusHead") {
+            self.configure(&packet.data)?;
+            return Ok(AudioSamples { pts: packet.pts, duration: Duration::ZERO, sample_rate: SAMPLE_RATE, channels: self.head.channels as u32, data: Vec::new() });
+        }
+        if packet.data.starts_with(b"OpusTags") {
+            return Ok(AudioSamples { pts: packet.pts, duration: Duration::ZERO, sample_rate: SAMPLE_RATE, channels: self.head.channels as u32, data: Vec::new() });
+        }
+        self.decode_audio_packet(&packet.data, packet.pts)
+    }
+    fn flush(&mut self) -> Option<AudioSamples> { None }
+    fn reset(&mut self) {
+        self.state.samples_decoded = 0;
+        for stream in &mut self.streams {
+            stream.reset();
+        }
+    }
+    fn sample_rate(&self) -> u32 { SAMPLE_RATE }
+    fn channels(&self) -> u32 { self.head.channels as u32 }
+    /// OpusHead identification header (Ogg, Matroska CodecPrivate, MP4 dOps
+    /// converted by the demuxer)
+    fn configure(&mut self, extradata: &[u8]) -> DecoderResult<()> {
+        *self = Self::with_head(OpusHead::parse(extradata)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toc_parsing() {
+        // CELT-only fullband 20 ms stereo
+        let toc = Toc::parse(0xfc);
+        assert_eq!((toc.mode, toc.bandwidth, toc.frame_size, toc.stereo), (Mode::CeltOnly, Bandwidth::Full, 960, true));
+        // SILK-only narrowband 60 ms mono
+        let toc = Toc::parse(0x18);
+        assert_eq!((toc.mode, toc.bandwidth, toc.frame_size, toc.stereo), (Mode::SilkOnly, Bandwidth::Narrow, 2880, false));
+        // Hybrid superwideband 10 ms
+        let toc = Toc::parse(0x60);
+        assert_eq!((toc.mode, toc.bandwidth, toc.frame_size), (Mode::Hybrid, Bandwidth::SuperWide, 480));
+    }
+
+    #[test]
+    fn test_packet_framing() {
+        // Code 3, VBR, padded: two frames of 2 and 3 bytes plus 2 bytes of padding
+        let packet = [0xfb, 0xc2, 0x02, 0x02, 1, 2, 3, 4, 5, 0, 0];
+        let (_, frames, used) = parse_packet(&packet, false).unwrap();
+        assert_eq!(frames, vec![&[1, 2][..], &[3, 4, 5][..]]);
+        assert_eq!(used, packet.len());
+        // Self-delimited code 1 with an explicit frame size
+        let packet = [0xf9, 0x02, 1, 2, 3, 4, 0xaa];
+        let (_, frames, used) = parse_packet(&packet, true).unwrap();
+        assert_eq!(frames, vec![&[1, 2][..], &[3, 4][..]]);
+        assert_eq!(used, 6);
+        assert!(parse_packet(&[0xf9, 1, 2, 3], false).is_err());
+    }
+
+    #[test]
+    fn test_opus_head() {
+        let mut head = b"OpusHead".to_vec();
+        head.extend_from_slice(&[1, 2, 0x38, 0x01, 0x80, 0xbb, 0, 0, 0, 0, 0]);
+        let head = OpusHead::parse(&head).unwrap();
+        assert_eq!((head.channels, head.pre_skip, head.input_sample_rate, head.coupled_count), (2, 312, 48000, 1));
+        assert!(OpusHead::parse(b"OpusHead").is_err());
+    }
+}
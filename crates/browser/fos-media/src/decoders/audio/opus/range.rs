@@ -0,0 +1,253 @@
+//! Opus Range Decoder
+//!
+//! Entropy decoder shared by SILK and CELT (RFC 6716 section 4.1): range
+//! coded symbols read from the front of the frame and raw bits read from the
+//! back.
+
+const SYM_BITS: u32 = 8;
+const CODE_BITS: u32 = 32;
+const CODE_TOP: u32 = 1 << (CODE_BITS - 1);
+const CODE_BOT: u32 = CODE_TOP >> SYM_BITS;
+const CODE_EXTRA: u32 = (CODE_BITS - 2) % SYM_BITS + 1;
+const UINT_BITS: u32 = 8;
+
+/// Fractional bits of [`RangeDecoder::tell_frac`]
+pub const BITRES: u32 = 3;
+
+/// Range decoder over one frame
+#[derive(Debug, Clone)]
+pub struct RangeDecoder<'a> {
+    buf: &'a [u8],
+    offs: usize,
+    end_offs: usize,
+    end_window: u32,
+    nend_bits: u32,
+    nbits_total: i32,
+    rng: u32,
+    val: u32,
+    ext: u32,
+    rem: u32,
+}
+
+impl<'a> RangeDecoder<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        let mut dec = Self {
+            buf,
+            offs: 0,
+            end_offs: 0,
+            end_window: 0,
+            nend_bits: 0,
+            nbits_total: (CODE_BITS + 1 - ((CODE_BITS - CODE_EXTRA) / SYM_BITS) * SYM_BITS) as i32,
+            rng: 1 << CODE_EXTRA,
+            val: 0,
+            ext: 0,
+            rem: 0,
+        };
+        dec.rem = dec.read_byte();
+        dec.val = dec.rng - 1 - (dec.rem >> (SYM_BITS - CODE_EXTRA));
+        dec.normalize();
+        dec
+    }
+
+    /// Bytes available to the decoder
+    pub fn storage(&self) -> usize { self.buf.len() }
+
+    /// Restrict the frame to its first `len` bytes (raw bits move with it)
+    pub fn shrink(&mut self, len: usize) {
+        self.buf = &self.buf[..len.min(self.buf.len())];
+    }
+
+    /// Final range state, compared against the encoder's to check decoding
+    pub fn range(&self) -> u32 { self.rng }
+
+    /// Account for every remaining bit as consumed (silent CELT frames)
+    pub fn skip_to_end(&mut self) {
+        self.nbits_total += (self.buf.len() * 8) as i32 - self.tell();
+    }
+
+    fn read_byte(&mut self) -> u32 {
+        if self.offs < self.buf.len() {
+            self.offs += 1;
+            self.buf[self.offs - 1] as u32
+        } else {
+            0
+        }
+    }
+
+    fn read_byte_from_end(&mut self) -> u32 {
+        if self.end_offs < self.buf.len() {
+            self.end_offs += 1;
+            self.buf[self.buf.len() - self.end_offs] as u32
+        } else {
+            0
+        }
+    }
+
+    fn normalize(&mut self) {
+        while self.rng <= CODE_BOT {
+            self.nbits_total += SYM_BITS as i32;
+            self.rng <<= SYM_BITS;
+            let mut sym = self.rem;
+            self.rem = self.read_byte();
+            sym = (sym << SYM_BITS | self.rem) >> (SYM_BITS - CODE_EXTRA);
+            self.val = ((self.val << SYM_BITS) + (0xFF & !sym)) & (CODE_TOP - 1);
+        }
+    }
+
+    /// Cumulative frequency of the next symbol out of `ft`; follow with
+    /// [`update`](Self::update)
+    pub fn decode(&mut self, ft: u32) -> u32 {
+        self.ext = self.rng / ft;
+        let s = self.val / self.ext;
+        ft - (s + 1).min(ft)
+    }
+
+    /// [`decode`](Self::decode) with `ft = 1 << bits`
+    pub fn decode_bin(&mut self, bits: u32) -> u32 {
+        self.ext = self.rng >> bits;
+        let s = self.val / self.ext;
+        (1 << bits) - (s + 1).min(1 << bits)
+    }
+
+    /// Consume the symbol spanning `[fl, fh)` out of `ft`
+    pub fn update(&mut self, fl: u32, fh: u32, ft: u32) {
+        let s = self.ext.wrapping_mul(ft - fh);
+        self.val = self.val.wrapping_sub(s);
+        self.rng = if fl > 0 { self.ext.wrapping_mul(fh - fl) } else { self.rng - s };
+        self.normalize();
+    }
+
+    /// A bit that is one with probability `1 / (1 << logp)`
+    pub fn bit_logp(&mut self, logp: u32) -> bool {
+        let (r, d) = (self.rng, self.val);
+        let s = r >> logp;
+        let ret = d < s;
+        if !ret { self.val = d - s; }
+        self.rng = if ret { s } else { r - s };
+        self.normalize();
+        ret
+    }
+
+    /// Symbol from an inverse cumulative distribution with total `1 << ftb`
+    pub fn icdf(&mut self, icdf: &[u8], ftb: u32) -> usize {
+        let mut s = self.rng;
+        let d = self.val;
+        let r = s >> ftb;
+        let mut ret = 0;
+        let mut t;
+        loop {
+            t = s;
+            s = r.wrapping_mul(icdf[ret] as u32);
+            if d >= s || ret + 1 >= icdf.len() { break; }
+            ret += 1;
+        }
+        self.val = d.wrapping_sub(s);
+        self.rng = t - s;
+        self.normalize();
+        ret
+    }
+
+    /// Uniform integer in `0..ft`
+    pub fn uint(&mut self, ft: u32) -> u32 {
+        debug_assert!(ft > 1);
+        let ft = ft - 1;
+        let mut ftb = 32 - ft.leading_zeros();
+        if ftb > UINT_BITS {
+            ftb -= UINT_BITS;
+            let top = (ft >> ftb) + 1;
+            let s = self.decode(top);
+            self.update(s, s + 1, top);
+            let t = s << ftb | self.bits(ftb);
+            // Out-of-range values only occur in corrupt frames
+            t.min(ft)
+        } else {
+            let s = self.decode(ft + 1);
+            self.update(s, s + 1, ft + 1);
+            s
+        }
+    }
+
+    /// Raw bits from the end of the frame
+    pub fn bits(&mut self, bits: u32) -> u32 {
+        let mut window = self.end_window as u64;
+        let mut available = self.nend_bits;
+        if available < bits {
+            loop {
+                window |= (self.read_byte_from_end() as u64) << available;
+                available += SYM_BITS;
+                if available > 32 - SYM_BITS { break; }
+            }
+        }
+        let ret = (window & ((1u64 << bits) - 1)) as u32;
+        window >>= bits;
+        available -= bits;
+        self.end_window = window as u32;
+        self.nend_bits = available;
+        self.nbits_total += bits as i32;
+        ret
+    }
+
+    /// Laplace-distributed energy delta (CELT coarse energy)
+    pub fn laplace(&mut self, mut fs: u32, decay: u32) -> i32 {
+        const MINP: u32 = 1;
+        const NMIN: u32 = 16;
+        let mut val = 0i32;
+        let fm = self.decode_bin(15);
+        let mut fl = 0;
+        if fm >= fs {
+            val += 1;
+            fl = fs;
+            fs = (((32768 - MINP * 2 * NMIN - fs) * (16384 - decay)) >> 15) + MINP;
+            while fs > MINP && fm >= fl + 2 * fs {
+                fs *= 2;
+                fl += fs;
+                fs = (((fs - 2 * MINP) * decay) >> 15) + MINP;
+                val += 1;
+            }
+            if fs <= MINP {
+                let di = (fm - fl) >> 1;
+                val += di as i32;
+                fl += 2 * di * MINP;
+            }
+            if fm < fl + fs {
+                val = -val;
+            } else {
+                fl += fs;
+            }
+        }
+        self.update(fl, (fl + fs).min(32768), 32768);
+        val
+    }
+
+    /// Bits consumed so far, rounded up
+    pub fn tell(&self) -> i32 { self.nbits_total - (32 - self.rng.leading_zeros()) as i32 }
+
+    /// Bits consumed so far in 1/8 bit units
+    pub fn tell_frac(&self) -> u32 {
+        let nbits = (self.nbits_total as u32) << BITRES;
+        let mut l = 32 - self.rng.leading_zeros();
+        let mut r = self.rng >> (l - 16);
+        for _ in 0..BITRES {
+            r = (r * r) >> 15;
+            let b = r >> 16;
+            l = l << 1 | b;
+            r >>= b;
+        }
+        nbits - l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_bits_from_end() {
+        let data = [0x00, 0x00, 0xA5, 0x3C];
+        let mut dec = RangeDecoder::new(&data);
+        assert_eq!(dec.tell(), 1);
+        assert_eq!(dec.bits(4), 0xC);
+        assert_eq!(dec.bits(8), 0x53);
+        assert_eq!(dec.tell(), 13);
+    }
+}
@@ -0,0 +1,521 @@
+//! CELT Bit Allocation
+//!
+//! Static 48 kHz mode tables, the PVQ pulse cache and the band allocation
+//! (RFC 6716 section 4.3.3). The pulse cache is computed once at startup
+//! rather than shipped as a table.
+
+use super::bands::ncwrs_urow;
+use super::range::{RangeDecoder, BITRES};
+
+pub const NB_EBANDS: usize = 21;
+pub const MAX_LM: usize = 3;
+pub const SHORT_MDCT_SIZE: usize = 120;
+pub const OVERLAP: usize = 120;
+
+/// Band edges in units of 2.5 ms MDCT bins
+pub const EBANDS: [i16; NB_EBANDS + 1] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 10, 12, 14, 16, 20, 24, 28, 34, 40, 48, 60, 78, 100];
+
+/// `log2` of each band width in 1/8 bits
+pub const LOG_N: [i16; NB_EBANDS] = [0, 0, 0, 0, 0, 0, 0, 0, 8, 8, 8, 8, 16, 16, 16, 21, 21, 24, 29, 34, 36];
+
+pub const MAX_FINE_BITS: i32 = 8;
+const FINE_OFFSET: i32 = 21;
+pub const QTHETA_OFFSET: i32 = 4;
+pub const QTHETA_OFFSET_TWOPHASE: i32 = 16;
+const MAX_PSEUDO: i32 = 40;
+const LOG_MAX_PSEUDO: usize = 6;
+const ALLOC_STEPS: usize = 6;
+const NB_ALLOC_VECTORS: usize = 11;
+
+const LOG2_FRAC_TABLE: [u8; 24] = [0, 8, 13, 16, 19, 21, 23, 24, 26, 27, 28, 29, 30, 31, 32, 32, 33, 34, 34, 35, 36, 36, 37, 37];
+
+/// Bits per band for each allocation quality, in 1/32 bit per MDCT bin
+#[rustfmt::skip]
+const BAND_ALLOCATION: [u8; NB_ALLOC_VECTORS * NB_EBANDS] = [
+      0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,
+     90,  80,  75,  69,  63,  56,  49,  40,  34,  29,  20,  18,  10,   0,   0,   0,   0,   0,   0,   0,   0,
+    110, 100,  90,  84,  78,  71,  65,  58,  51,  45,  39,  32,  26,  20,  12,   0,   0,   0,   0,   0,   0,
+    118, 110, 103,  93,  86,  80,  75,  70,  65,  59,  53,  47,  40,  31,  23,  15,   4,   0,   0,   0,   0,
+    126, 119, 112, 104,  95,  89,  83,  78,  72,  66,  60,  54,  47,  39,  32,  25,  17,  12,   1,   0,   0,
+    134, 127, 120, 114, 103,  97,  91,  85,  78,  72,  66,  60,  54,  47,  41,  35,  29,  23,  16,  10,   1,
+    144, 137, 130, 124, 113, 107, 101,  95,  88,  82,  76,  70,  64,  57,  51,  45,  39,  33,  26,  15,   1,
+    152, 145, 138, 132, 123, 117, 111, 105,  98,  92,  86,  80,  74,  67,  61,  55,  49,  43,  36,  20,   1,
+    162, 155, 148, 142, 133, 127, 121, 115, 108, 102,  96,  90,  84,  77,  71,  65,  59,  53,  46,  30,   1,
+    172, 165, 158, 152, 143, 137, 131, 125, 118, 112, 106, 100,  94,  87,  81,  75,  69,  63,  56,  45,  20,
+    200, 200, 200, 200, 200, 200, 200, 200, 198, 193, 188, 183, 178, 173, 168, 163, 158, 153, 148, 129, 104,
+];
+
+fn band_width(j: usize) -> i32 { (EBANDS[j + 1] - EBANDS[j]) as i32 }
+
+fn ilog(v: u32) -> i32 { 32 - v.leading_zeros() as i32 }
+
+/// Pulse count for a pseudo-pulse index
+pub fn get_pulses(i: i32) -> i32 {
+    if i < 8 { i } else { (8 + (i & 7)) << ((i >> 3) - 1) }
+}
+
+/// PVQ codebook sizes in 1/8 bits, per band size and pulse count
+#[derive(Debug)]
+pub struct PulseCache {
+    index: Vec<i16>,
+    bits: Vec<u8>,
+    caps: Vec<u8>,
+}
+
+impl PulseCache {
+    pub fn new() -> Self {
+        let lm = MAX_LM;
+        let mut index = vec![-1i16; NB_EBANDS * (lm + 2)];
+        let mut entries: Vec<(i32, i32, usize)> = Vec::new();
+        let mut curr = 0usize;
+        for i in 0..=lm + 1 {
+            for j in 0..NB_EBANDS {
+                let n = band_width(j) << i >> 1;
+                // Share the entry of an earlier band with the same size
+                'search: for k in 0..=i {
+                    for m in 0..NB_EBANDS {
+                        if k == i && m >= j { break; }
+                        if n == band_width(m) << k >> 1 {
+                            index[i * NB_EBANDS + j] = index[k * NB_EBANDS + m];
+                            break 'search;
+                        }
+                    }
+                }
+                if index[i * NB_EBANDS + j] == -1 && n != 0 {
+                    let mut k = 0;
+                    while fits_in32(n, get_pulses(k + 1)) && k < MAX_PSEUDO {
+                        k += 1;
+                    }
+                    entries.push((n, k, curr));
+                    index[i * NB_EBANDS + j] = curr as i16;
+                    curr += k as usize + 1;
+                }
+            }
+        }
+        let mut bits = vec![0u8; curr];
+        for &(n, k, offset) in &entries {
+            let required = required_bits(n, get_pulses(k));
+            for j in 1..=k as usize {
+                bits[offset + j] = (required[get_pulses(j as i32) as usize] - 1) as u8;
+            }
+            bits[offset] = k as u8;
+        }
+
+        // Highest rate at which each band reliably spends what it is given
+        let mut caps = Vec::with_capacity((lm + 1) * 2 * NB_EBANDS);
+        for i in 0..=lm as i32 {
+            for c in 1..=2i32 {
+                for j in 0..NB_EBANDS {
+                    let mut n0 = band_width(j);
+                    let max_bits = if n0 << i == 1 {
+                        (c * (1 + MAX_FINE_BITS)) << BITRES
+                    } else {
+                        let mut lm0 = 0i32;
+                        if n0 > 2 {
+                            n0 >>= 1;
+                            lm0 -= 1;
+                        } else if n0 <= 1 {
+                            lm0 = i.min(1);
+                            n0 <<= lm0;
+                        }
+                        let pcache = &bits[index[(lm0 + 1) as usize * NB_EBANDS + j] as usize..];
+                        let mut max_bits = pcache[pcache[0] as usize] as i32 + 1;
+                        let mut n = n0;
+                        for k in 0..i - lm0 {
+                            max_bits <<= 1;
+                            let offset = ((LOG_N[j] as i32 + ((lm0 + k) << BITRES)) >> 1) - QTHETA_OFFSET;
+                            let num = 459 * ((2 * n - 1) * offset + max_bits);
+                            let den = ((2 * n - 1) << 9) - 459;
+                            max_bits += ((num + (den >> 1)) / den).min(57);
+                            n <<= 1;
+                        }
+                        if c == 2 {
+                            max_bits <<= 1;
+                            let offset = ((LOG_N[j] as i32 + (i << BITRES)) >> 1) - if n == 2 { QTHETA_OFFSET_TWOPHASE } else { QTHETA_OFFSET };
+                            let ndof = 2 * n - 1 - (n == 2) as i32;
+                            let scale = if n == 2 { 512 } else { 487 };
+                            let num = scale * (max_bits + ndof * offset);
+                            let den = (ndof << 9) - scale;
+                            max_bits += ((num + (den >> 1)) / den).min(if n == 2 { 64 } else { 61 });
+                        }
+                        let ndof = c * n + (c == 2 && n > 2) as i32;
+                        let mut offset = ((LOG_N[j] as i32 + (i << BITRES)) >> 1) - FINE_OFFSET;
+                        if n == 2 {
+                            offset += 1 << BITRES >> 2;
+                        }
+                        let num = max_bits + ndof * offset;
+                        let den = (ndof - 1) << BITRES;
+                        let qb = ((num + (den >> 1)) / den).min(MAX_FINE_BITS);
+                        max_bits + ((c * qb) << BITRES)
+                    };
+                    caps.push((4 * max_bits / (c * (band_width(j) << i)) - 64) as u8);
+                }
+            }
+        }
+        Self { index, bits, caps }
+    }
+
+    /// Cache row for a band at split depth `lm` (which may be -1)
+    fn row(&self, band: usize, lm: i32) -> &[u8] {
+        &self.bits[self.index[(lm + 1) as usize * NB_EBANDS + band] as usize..]
+    }
+
+    /// Cost of the largest codebook before the band splits, in 1/8 bits
+    pub fn split_threshold(&self, band: usize, lm: i32) -> i32 {
+        let cache = self.row(band, lm);
+        cache[cache[0] as usize] as i32
+    }
+
+    pub fn bits2pulses(&self, band: usize, lm: i32, bits: i32) -> i32 {
+        let cache = self.row(band, lm);
+        let (mut lo, mut hi) = (0usize, cache[0] as usize);
+        let bits = bits - 1;
+        for _ in 0..LOG_MAX_PSEUDO {
+            let mid = (lo + hi + 1) >> 1;
+            if cache[mid] as i32 >= bits { hi = mid; } else { lo = mid; }
+        }
+        let below = if lo == 0 { -1 } else { cache[lo] as i32 };
+        if bits - below <= cache[hi] as i32 - bits { lo as i32 } else { hi as i32 }
+    }
+
+    pub fn pulses2bits(&self, band: usize, lm: i32, pulses: i32) -> i32 {
+        if pulses == 0 { 0 } else { self.row(band, lm)[pulses as usize] as i32 + 1 }
+    }
+
+    /// Per-band allocation ceilings for a frame
+    pub fn init_caps(&self, lm: usize, c: usize) -> [i32; NB_EBANDS] {
+        let mut cap = [0; NB_EBANDS];
+        for (i, cap) in cap.iter_mut().enumerate() {
+            let n = band_width(i) << lm;
+            *cap = ((self.caps[NB_EBANDS * (2 * lm + c - 1) + i] as i32 + 64) * c as i32 * n) >> 2;
+        }
+        cap
+    }
+}
+
+/// Whether V(n, k) fits in 32 bits
+fn fits_in32(n: i32, k: i32) -> bool {
+    const MAX_N: [i32; 15] = [32767, 32767, 32767, 1476, 283, 109, 60, 40, 29, 24, 20, 18, 16, 14, 13];
+    const MAX_K: [i32; 15] = [32767, 32767, 32767, 32767, 1172, 238, 95, 53, 36, 27, 22, 18, 16, 15, 13];
+    if n >= 14 {
+        k < 14 && n <= MAX_N[k as usize]
+    } else {
+        k <= MAX_K[n as usize]
+    }
+}
+
+/// `log2(val)` rounded up with `frac` fractional bits
+fn log2_frac(mut val: u32, mut frac: i32) -> i32 {
+    let mut l = ilog(val);
+    if val & (val - 1) == 0 {
+        return (l - 1) << frac;
+    }
+    val = if l > 16 { ((val - 1) >> (l - 16)) + 1 } else { val << (16 - l) };
+    l = (l - 1) << frac;
+    loop {
+        let b = (val >> 16) as i32;
+        l += b << frac;
+        val = (val + b as u32) >> b;
+        val = (val * val + 0x7FFF) >> 15;
+        if frac == 0 { break; }
+        frac -= 1;
+    }
+    l + (val > 0x8000) as i32
+}
+
+/// Codebook size in 1/8 bits for every pulse count up to `max_k`
+fn required_bits(n: i32, max_k: i32) -> Vec<i32> {
+    let mut bits = vec![0; max_k as usize + 1];
+    if n == 1 {
+        for b in &mut bits[1..] {
+            *b = 1 << BITRES;
+        }
+    } else {
+        let mut u = vec![0u32; max_k as usize + 2];
+        ncwrs_urow(n as usize, max_k as usize, &mut u);
+        for k in 1..=max_k as usize {
+            bits[k] = log2_frac(u[k].wrapping_add(u[k + 1]), BITRES as i32);
+        }
+    }
+    bits
+}
+
+/// Result of [`compute_allocation`]
+#[derive(Debug, Default)]
+pub struct Allocation {
+    pub coded_bands: usize,
+    pub intensity: usize,
+    pub dual_stereo: bool,
+    pub balance: i32,
+    pub pulses: [i32; NB_EBANDS],
+    pub fine_quant: [i32; NB_EBANDS],
+    pub fine_priority: [i32; NB_EBANDS],
+}
+
+/// Split `total` 1/8 bits between PVQ and fine energy for each band
+#[allow(clippy::too_many_arguments)]
+pub fn compute_allocation(
+    start: usize,
+    end: usize,
+    offsets: &[i32; NB_EBANDS],
+    cap: &[i32; NB_EBANDS],
+    alloc_trim: i32,
+    total: i32,
+    c: usize,
+    lm: usize,
+    dec: &mut RangeDecoder,
+) -> Allocation {
+    let ci = c as i32;
+    let mut total = total.max(0);
+    let skip_rsv = if total >= 1 << BITRES { 1 << BITRES } else { 0 };
+    total -= skip_rsv;
+    let (mut intensity_rsv, mut dual_stereo_rsv) = (0, 0);
+    if c == 2 {
+        intensity_rsv = LOG2_FRAC_TABLE[end - start] as i32;
+        if intensity_rsv > total {
+            intensity_rsv = 0;
+        } else {
+            total -= intensity_rsv;
+            dual_stereo_rsv = if total >= 1 << BITRES { 1 << BITRES } else { 0 };
+            total -= dual_stereo_rsv;
+        }
+    }
+    let mut thresh = [0i32; NB_EBANDS];
+    let mut trim_offset = [0i32; NB_EBANDS];
+    for j in start..end {
+        thresh[j] = (ci << BITRES).max((3 * band_width(j)) << lm << BITRES >> 4);
+        trim_offset[j] = (ci * band_width(j) * (alloc_trim - 5 - lm as i32) * (end - j - 1) as i32 * (1 << (lm as u32 + BITRES))) >> 6;
+        if band_width(j) << lm == 1 {
+            trim_offset[j] -= ci << BITRES;
+        }
+    }
+    let vector = |q: usize, j: usize| (ci * band_width(j) * BAND_ALLOCATION[q * NB_EBANDS + j] as i32 * (1 << lm)) >> 2;
+    let (mut lo, mut hi) = (1i32, NB_ALLOC_VECTORS as i32 - 1);
+    loop {
+        let mut done = false;
+        let mut psum = 0;
+        let mid = ((lo + hi) >> 1) as usize;
+        for j in (start..end).rev() {
+            let mut bitsj = vector(mid, j);
+            if bitsj > 0 {
+                bitsj = (bitsj + trim_offset[j]).max(0);
+            }
+            bitsj += offsets[j];
+            if bitsj >= thresh[j] || done {
+                done = true;
+                psum += bitsj.min(cap[j]);
+            } else if bitsj >= ci << BITRES {
+                psum += ci << BITRES;
+            }
+        }
+        if psum > total { hi = mid as i32 - 1; } else { lo = mid as i32 + 1; }
+        if lo > hi { break; }
+    }
+    hi = lo;
+    lo -= 1;
+    let mut skip_start = start;
+    let mut bits1 = [0i32; NB_EBANDS];
+    let mut bits2 = [0i32; NB_EBANDS];
+    for j in start..end {
+        let mut bits1j = vector(lo as usize, j);
+        let mut bits2j = if hi as usize >= NB_ALLOC_VECTORS { cap[j] } else { vector(hi as usize, j) };
+        if bits1j > 0 {
+            bits1j = (bits1j + trim_offset[j]).max(0);
+        }
+        if bits2j > 0 {
+            bits2j = (bits2j + trim_offset[j]).max(0);
+        }
+        if lo > 0 {
+            bits1j += offsets[j];
+        }
+        bits2j += offsets[j];
+        if offsets[j] > 0 {
+            skip_start = j;
+        }
+        bits1[j] = bits1j;
+        bits2[j] = (bits2j - bits1j).max(0);
+    }
+    interp_bits2pulses(
+        start, end, skip_start, &bits1, &bits2, &thresh, cap, total, skip_rsv, intensity_rsv, dual_stereo_rsv, c, lm, dec,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn interp_bits2pulses(
+    start: usize,
+    end: usize,
+    skip_start: usize,
+    bits1: &[i32; NB_EBANDS],
+    bits2: &[i32; NB_EBANDS],
+    thresh: &[i32; NB_EBANDS],
+    cap: &[i32; NB_EBANDS],
+    mut total: i32,
+    skip_rsv: i32,
+    mut intensity_rsv: i32,
+    mut dual_stereo_rsv: i32,
+    c: usize,
+    lm: usize,
+    dec: &mut RangeDecoder,
+) -> Allocation {
+    let ci = c as i32;
+    let alloc_floor = ci << BITRES;
+    let stereo = (c > 1) as i32;
+    let log_m = (lm as i32) << BITRES;
+    let mut out = Allocation::default();
+    let bits = &mut out.pulses;
+    let ebits = &mut out.fine_quant;
+    let fine_priority = &mut out.fine_priority;
+
+    let (mut lo, mut hi) = (0i32, 1i32 << ALLOC_STEPS);
+    for _ in 0..ALLOC_STEPS {
+        let mid = (lo + hi) >> 1;
+        let mut psum = 0;
+        let mut done = false;
+        for j in (start..end).rev() {
+            let tmp = bits1[j] + ((mid * bits2[j]) >> ALLOC_STEPS);
+            if tmp >= thresh[j] || done {
+                done = true;
+                psum += tmp.min(cap[j]);
+            } else if tmp >= alloc_floor {
+                psum += alloc_floor;
+            }
+        }
+        if psum > total { hi = mid; } else { lo = mid; }
+    }
+    let mut psum = 0;
+    let mut done = false;
+    for j in (start..end).rev() {
+        let mut tmp = bits1[j] + ((lo * bits2[j]) >> ALLOC_STEPS);
+        if tmp < thresh[j] && !done {
+            tmp = if tmp >= alloc_floor { alloc_floor } else { 0 };
+        } else {
+            done = true;
+        }
+        tmp = tmp.min(cap[j]);
+        bits[j] = tmp;
+        psum += tmp;
+    }
+
+    // Skip bands from the top, reclaiming their bits
+    let mut coded_bands = end;
+    loop {
+        let j = coded_bands - 1;
+        if j <= skip_start {
+            total += skip_rsv;
+            break;
+        }
+        let span = (EBANDS[coded_bands] - EBANDS[start]) as i32;
+        let mut left = total - psum;
+        let percoeff = (left as u32 / span as u32) as i32;
+        left -= span * percoeff;
+        let rem = (left - (EBANDS[j] - EBANDS[start]) as i32).max(0);
+        let width = (EBANDS[coded_bands] - EBANDS[j]) as i32;
+        let mut band_bits = bits[j] + percoeff * width + rem;
+        if band_bits >= thresh[j].max(alloc_floor + (1 << BITRES)) {
+            if dec.bit_logp(1) {
+                break;
+            }
+            psum += 1 << BITRES;
+            band_bits -= 1 << BITRES;
+        }
+        psum -= bits[j] + intensity_rsv;
+        if intensity_rsv > 0 {
+            intensity_rsv = LOG2_FRAC_TABLE[j - start] as i32;
+        }
+        psum += intensity_rsv;
+        if band_bits >= alloc_floor {
+            psum += alloc_floor;
+            bits[j] = alloc_floor;
+        } else {
+            bits[j] = 0;
+        }
+        coded_bands -= 1;
+    }
+
+    out.intensity = if intensity_rsv > 0 { start + dec.uint((coded_bands + 1 - start) as u32) as usize } else { 0 };
+    if out.intensity <= start {
+        total += dual_stereo_rsv;
+        dual_stereo_rsv = 0;
+    }
+    out.dual_stereo = dual_stereo_rsv > 0 && dec.bit_logp(1);
+
+    // Spread what is left evenly over the coded bands
+    let span = (EBANDS[coded_bands] - EBANDS[start]) as i32;
+    let mut left = total - psum;
+    let percoeff = (left as u32 / span as u32) as i32;
+    left -= span * percoeff;
+    for (j, b) in bits.iter_mut().enumerate().take(coded_bands).skip(start) {
+        *b += percoeff * band_width(j);
+    }
+    for (j, b) in bits.iter_mut().enumerate().take(coded_bands).skip(start) {
+        let tmp = left.min(band_width(j));
+        *b += tmp;
+        left -= tmp;
+    }
+
+    let mut balance = 0;
+    for j in start..coded_bands {
+        let n = band_width(j) << lm;
+        let bit = bits[j] + balance;
+        let mut excess;
+        if n > 1 {
+            excess = (bit - cap[j]).max(0);
+            bits[j] = bit - excess;
+            let den = ci * n + (c == 2 && n > 2 && !out.dual_stereo && j < out.intensity) as i32;
+            let nc_log_n = den * (LOG_N[j] as i32 + log_m);
+            let mut offset = (nc_log_n >> 1) - den * FINE_OFFSET;
+            if n == 2 {
+                offset += den << BITRES >> 2;
+            }
+            if bits[j] + offset < (den * 2) << BITRES {
+                offset += nc_log_n >> 2;
+            } else if bits[j] + offset < (den * 3) << BITRES {
+                offset += nc_log_n >> 3;
+            }
+            ebits[j] = (bits[j] + offset + (den << (BITRES - 1))).max(0);
+            ebits[j] = ((ebits[j] as u32 / den as u32) >> BITRES) as i32;
+            if ci * ebits[j] > bits[j] >> BITRES {
+                ebits[j] = bits[j] >> stereo >> BITRES;
+            }
+            ebits[j] = ebits[j].min(MAX_FINE_BITS);
+            fine_priority[j] = (ebits[j] * (den << BITRES) >= bits[j] + offset) as i32;
+            bits[j] -= (ci * ebits[j]) << BITRES;
+        } else {
+            excess = (bit - (ci << BITRES)).max(0);
+            bits[j] = bit - excess;
+            ebits[j] = 0;
+            fine_priority[j] = 1;
+        }
+        if excess > 0 {
+            let extra_fine = (excess >> (stereo + BITRES as i32)).min(MAX_FINE_BITS - ebits[j]);
+            ebits[j] += extra_fine;
+            let extra_bits = (extra_fine * ci) << BITRES;
+            fine_priority[j] = (extra_bits >= excess - balance) as i32;
+            excess -= extra_bits;
+        }
+        balance = excess;
+    }
+    out.balance = balance;
+    // Skipped bands spend everything on fine energy
+    for j in coded_bands..end {
+        ebits[j] = bits[j] >> stereo >> BITRES;
+        bits[j] = 0;
+        fine_priority[j] = (ebits[j] < 1) as i32;
+    }
+    out.coded_bands = coded_bands;
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pulse_cache_matches_reference_mode() {
+        let cache = PulseCache::new();
+        assert_eq!(cache.bits.len(), 392);
+        assert_eq!(&cache.index[..21], &[-1, -1, -1, -1, -1, -1, -1, -1, 0, 0, 0, 0, 41, 41, 41, 82, 82, 123, 164, 200, 222]);
+        assert_eq!(&cache.bits[..4], &[40, 7, 7, 7]);
+        assert_eq!(&cache.caps[..21], &[224, 224, 224, 224, 224, 224, 224, 224, 160, 160, 160, 160, 185, 185, 185, 178, 178, 168, 134, 61, 37]);
+    }
+}
@@ -0,0 +1,219 @@
+//! SILK Fixed-Point Arithmetic
+//!
+//! The 16/32-bit multiply-accumulate primitives SILK is specified in. The
+//! decoder output is bit-exact, so these keep the reference truncation and
+//! wrap-around behaviour.
+
+/// `(a * (b as i16)) >> 16`
+#[inline]
+pub fn smulwb(a: i32, b: i32) -> i32 {
+    ((a as i64 * (b as i16) as i64) >> 16) as i32
+}
+
+/// `a + ((b * (c as i16)) >> 16)`
+#[inline]
+pub fn smlawb(a: i32, b: i32, c: i32) -> i32 {
+    a.wrapping_add(smulwb(b, c))
+}
+
+/// `(a * b) >> 16`
+#[inline]
+pub fn smulww(a: i32, b: i32) -> i32 {
+    ((a as i64 * b as i64) >> 16) as i32
+}
+
+/// `a + ((b * c) >> 16)`
+#[inline]
+pub fn smlaww(a: i32, b: i32, c: i32) -> i32 {
+    a.wrapping_add(smulww(b, c))
+}
+
+/// `(a as i16) * (b as i16)`
+#[inline]
+pub fn smulbb(a: i32, b: i32) -> i32 {
+    (a as i16 as i32) * (b as i16 as i32)
+}
+
+/// `a + (b as i16) * (c as i16)`
+#[inline]
+pub fn smlabb(a: i32, b: i32, c: i32) -> i32 {
+    a.wrapping_add(smulbb(b, c))
+}
+
+/// `(a >> 16) * (b >> 16)`
+#[inline]
+pub fn smultt(a: i32, b: i32) -> i32 {
+    (a >> 16) * (b >> 16)
+}
+
+/// `(a * b) >> 32`
+#[inline]
+pub fn smmul(a: i32, b: i32) -> i32 {
+    ((a as i64 * b as i64) >> 32) as i32
+}
+
+/// Rounding right shift, `shift > 0`
+#[inline]
+pub fn rshift_round(a: i32, shift: u32) -> i32 {
+    if shift == 1 {
+        (a >> 1) + (a & 1)
+    } else {
+        ((a >> (shift - 1)) + 1) >> 1
+    }
+}
+
+/// Rounding right shift of a 64-bit value, `shift > 0`
+#[inline]
+pub fn rshift_round64(a: i64, shift: u32) -> i64 {
+    if shift == 1 {
+        (a >> 1) + (a & 1)
+    } else {
+        ((a >> (shift - 1)) + 1) >> 1
+    }
+}
+
+#[inline]
+pub fn sat16(a: i32) -> i16 {
+    a.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
+
+/// Clamp `a` between two limits given in either order
+#[inline]
+pub fn limit(a: i32, limit1: i32, limit2: i32) -> i32 {
+    if limit1 > limit2 {
+        a.clamp(limit2, limit1)
+    } else {
+        a.clamp(limit1, limit2)
+    }
+}
+
+/// Left shift that saturates instead of overflowing
+#[inline]
+pub fn lshift_sat32(a: i32, shift: u32) -> i32 {
+    limit(a, i32::MIN >> shift, i32::MAX >> shift) << shift
+}
+
+/// Linear congruential generator of the excitation and comfort noise
+#[inline]
+pub fn rand(seed: i32) -> i32 {
+    907_633_515i32.wrapping_add(seed.wrapping_mul(196_314_165))
+}
+
+#[inline]
+pub fn clz32(a: i32) -> i32 {
+    (a as u32).leading_zeros() as i32
+}
+
+/// Leading zeros and the 7 bits that follow the leading one
+#[inline]
+pub fn clz_frac(a: i32) -> (i32, i32) {
+    let lz = clz32(a);
+    let frac = (a as u32).rotate_right((24 - lz).rem_euclid(32) as u32) as i32 & 0x7f;
+    (lz, frac)
+}
+
+/// Approximation of `sqrt(x)` with about 10 bits of precision
+pub fn sqrt_approx(x: i32) -> i32 {
+    if x <= 0 {
+        return 0;
+    }
+    let (lz, frac) = clz_frac(x);
+    let mut y = if lz & 1 != 0 { 32768 } else { 46214 };
+    y >>= lz >> 1;
+    smlawb(y, y, smulbb(213, frac))
+}
+
+/// Approximation of `(a << q_res) / b`
+pub fn div32_varq(a: i32, b: i32, q_res: i32) -> i32 {
+    let a_headrm = clz32(a.wrapping_abs()) - 1;
+    let mut a_nrm = a.wrapping_shl(a_headrm as u32);
+    let b_headrm = clz32(b.wrapping_abs()) - 1;
+    let b_nrm = b.wrapping_shl(b_headrm as u32);
+    // Inverse of b with 14 bits of precision
+    let b_inv = (i32::MAX >> 2) / (b_nrm >> 16);
+    let mut result = smulwb(a_nrm, b_inv);
+    // Refine with the residual; the wrap-around is intended
+    a_nrm = a_nrm.wrapping_sub(smmul(b_nrm, result).wrapping_shl(3));
+    result = smlawb(result, a_nrm, b_inv);
+    let lshift = 29 + a_headrm - b_headrm - q_res;
+    if lshift < 0 {
+        lshift_sat32(result, (-lshift) as u32)
+    } else if lshift < 32 {
+        result >> lshift
+    } else {
+        0
+    }
+}
+
+/// Approximation of `(1 << q_res) / b`
+pub fn inverse32_varq(b: i32, q_res: i32) -> i32 {
+    let b_headrm = clz32(b.wrapping_abs()) - 1;
+    let b_nrm = b.wrapping_shl(b_headrm as u32);
+    let b_inv = (i32::MAX >> 2) / (b_nrm >> 16);
+    let mut result = b_inv << 16;
+    let err_q32 = ((1 << 29) - smulwb(b_nrm, b_inv)).wrapping_shl(3);
+    result = smlaww(result, err_q32, b_inv);
+    let lshift = 61 - b_headrm - q_res;
+    if lshift <= 0 {
+        lshift_sat32(result, (-lshift) as u32)
+    } else if lshift < 32 {
+        result >> lshift
+    } else {
+        0
+    }
+}
+
+/// Approximation of `2^(x / 128)`
+pub fn log2lin(x: i32) -> i32 {
+    if x < 0 {
+        return 0;
+    } else if x >= 3967 {
+        return i32::MAX;
+    }
+    let out = 1 << (x >> 7);
+    let frac = x & 0x7f;
+    let poly = smlawb(frac, smulbb(frac, 128 - frac), -174);
+    if x < 2048 {
+        out + ((out * poly) >> 7)
+    } else {
+        out + (out >> 7) * poly
+    }
+}
+
+/// Energy of `x` and the right shift that keeps it within 32 bits
+pub fn sum_sqr_shift(x: &[i16]) -> (i32, i32) {
+    let len = x.len() as i32;
+    let accumulate = |shift: i32, init: i32| {
+        let mut nrg = init;
+        for pair in x.chunks(2) {
+            let mut tmp = smulbb(pair[0] as i32, pair[0] as i32) as u32;
+            if let Some(&b) = pair.get(1) {
+                tmp = tmp.wrapping_add(smulbb(b as i32, b as i32) as u32);
+            }
+            nrg = (nrg as u32).wrapping_add(tmp >> shift) as i32;
+        }
+        nrg
+    };
+    // First run with the largest shift that could be needed
+    let shift = 31 - clz32(len);
+    let nrg = accumulate(shift, len);
+    // Leave two bits of headroom
+    let shift = (shift + 3 - clz32(nrg)).max(0);
+    (accumulate(shift, 0), shift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log2lin_approximation() {
+        for k in 0..30 {
+            assert_eq!(log2lin(128 * k), 1 << k);
+        }
+        // 2^10.5 = 1448.15
+        assert!((log2lin(128 * 10 + 64) - 1448).abs() <= 8);
+        assert_eq!(rshift_round(5, 1), 3);
+        assert!((inverse32_varq(1 << 16, 32) - (1 << 16)).abs() <= 1);
+    }
+}
@@ -0,0 +1,353 @@
+//! SILK Linear Prediction
+//!
+//! NLSF dequantisation and stabilisation, conversion of the NLSFs to LPC
+//! coefficients with the stability checks of RFC 6716 section 4.2.7.5, and
+//! the bandwidth expansion and whitening filters shared by the decoder and
+//! its concealment.
+
+use super::fixed::*;
+use super::tables::*;
+
+pub const MAX_LPC_ORDER: usize = 16;
+
+/// Inverse prediction gain below which the filter counts as unstable
+const MIN_INV_PRED_GAIN_Q30: i32 = 107_374; // (1 / 1e4) in Q30
+const MAX_LPC_STABILIZE_ITERATIONS: i32 = 16;
+const NLSF_QUANT_LEVEL_ADJ_Q10: i32 = 102; // 0.1 in Q10
+
+/// One of the two NLSF codebooks (NB/MB and WB)
+#[derive(Debug)]
+pub struct NlsfCodebook {
+    pub n_vectors: usize,
+    pub order: usize,
+    pub quant_step_size_q16: i32,
+    pub cb1_nlsf_q8: &'static [u8],
+    pub cb1_wght_q9: &'static [i16],
+    pub cb1_icdf: &'static [u8],
+    pub pred_q8: &'static [u8],
+    pub ec_sel: &'static [u8],
+    pub ec_icdf: &'static [u8],
+    pub delta_min_q15: &'static [i16],
+}
+
+pub static NLSF_CB_NB_MB: NlsfCodebook = NlsfCodebook {
+    n_vectors: 32,
+    order: 10,
+    quant_step_size_q16: 11796, // 0.18 in Q16
+    cb1_nlsf_q8: &NLSF_CB1_NB_MB_Q8,
+    cb1_wght_q9: &NLSF_CB1_WGHT_Q9,
+    cb1_icdf: &NLSF_CB1_ICDF_NB_MB,
+    pred_q8: &NLSF_PRED_NB_MB_Q8,
+    ec_sel: &NLSF_CB2_SELECT_NB_MB,
+    ec_icdf: &NLSF_CB2_ICDF_NB_MB,
+    delta_min_q15: &NLSF_DELTA_MIN_NB_MB_Q15,
+};
+
+pub static NLSF_CB_WB: NlsfCodebook = NlsfCodebook {
+    n_vectors: 32,
+    order: 16,
+    quant_step_size_q16: 9830, // 0.15 in Q16
+    cb1_nlsf_q8: &NLSF_CB1_WB_Q8,
+    cb1_wght_q9: &NLSF_CB1_WB_WGHT_Q9,
+    cb1_icdf: &NLSF_CB1_ICDF_WB,
+    pred_q8: &NLSF_PRED_WB_Q8,
+    ec_sel: &NLSF_CB2_SELECT_WB,
+    ec_icdf: &NLSF_CB2_ICDF_WB,
+    delta_min_q15: &NLSF_DELTA_MIN_WB_Q15,
+};
+
+impl NlsfCodebook {
+    /// Entropy table offsets and backward predictor for a first stage index
+    pub fn unpack(&self, cb1_index: usize) -> ([usize; MAX_LPC_ORDER], [u8; MAX_LPC_ORDER]) {
+        let mut ec_ix = [0; MAX_LPC_ORDER];
+        let mut pred_q8 = [0; MAX_LPC_ORDER];
+        let sel = &self.ec_sel[cb1_index * self.order / 2..];
+        for i in (0..self.order).step_by(2) {
+            let entry = sel[i / 2] as usize;
+            ec_ix[i] = ((entry >> 1) & 7) * 9;
+            pred_q8[i] = self.pred_q8[i + (entry & 1) * (self.order - 1)];
+            ec_ix[i + 1] = ((entry >> 5) & 7) * 9;
+            pred_q8[i + 1] = self.pred_q8[i + ((entry >> 4) & 1) * (self.order - 1) + 1];
+        }
+        (ec_ix, pred_q8)
+    }
+
+    /// Dequantise an NLSF vector from its codebook path
+    pub fn decode(&self, indices: &[i8; MAX_LPC_ORDER + 1]) -> [i16; MAX_LPC_ORDER] {
+        let order = self.order;
+        let cb1 = indices[0] as usize;
+        let (_, pred_q8) = self.unpack(cb1);
+
+        // Predictive residual dequantiser
+        let mut res_q10 = [0i32; MAX_LPC_ORDER];
+        let mut out_q10 = 0i32;
+        for i in (0..order).rev() {
+            let pred_q10 = smulbb(out_q10, pred_q8[i] as i32) >> 8;
+            out_q10 = (indices[i + 1] as i32) << 10;
+            if out_q10 > 0 {
+                out_q10 -= NLSF_QUANT_LEVEL_ADJ_Q10;
+            } else if out_q10 < 0 {
+                out_q10 += NLSF_QUANT_LEVEL_ADJ_Q10;
+            }
+            out_q10 = smlawb(pred_q10, out_q10, self.quant_step_size_q16);
+            res_q10[i] = out_q10;
+        }
+
+        let mut nlsf_q15 = [0i16; MAX_LPC_ORDER];
+        let cb_element = &self.cb1_nlsf_q8[cb1 * order..];
+        let cb_wght = &self.cb1_wght_q9[cb1 * order..];
+        for i in 0..order {
+            let tmp = (((res_q10[i] as i16 as i32) << 14) / cb_wght[i] as i32) + ((cb_element[i] as i32) << 7);
+            nlsf_q15[i] = tmp.clamp(0, 32767) as i16;
+        }
+        nlsf_stabilize(&mut nlsf_q15[..order], self.delta_min_q15);
+        nlsf_q15
+    }
+}
+
+/// Enforce the minimum spacing between NLSFs (RFC 6716 section 4.2.7.5.4)
+pub fn nlsf_stabilize(nlsf: &mut [i16], delta_min: &[i16]) {
+    const MAX_LOOPS: usize = 20;
+    let l = nlsf.len();
+    for _ in 0..MAX_LOOPS {
+        // Find the smallest distance
+        let mut min_diff = nlsf[0] as i32 - delta_min[0] as i32;
+        let mut at = 0;
+        for i in 1..l {
+            let diff = nlsf[i] as i32 - (nlsf[i - 1] as i32 + delta_min[i] as i32);
+            if diff < min_diff {
+                min_diff = diff;
+                at = i;
+            }
+        }
+        let diff = (1 << 15) - (nlsf[l - 1] as i32 + delta_min[l] as i32);
+        if diff < min_diff {
+            min_diff = diff;
+            at = l;
+        }
+        if min_diff >= 0 {
+            return;
+        }
+
+        if at == 0 {
+            nlsf[0] = delta_min[0];
+        } else if at == l {
+            nlsf[l - 1] = ((1 << 15) - delta_min[l] as i32) as i16;
+        } else {
+            // Move the pair apart around its centre frequency
+            let half = (delta_min[at] >> 1) as i32;
+            let min_center = delta_min[..at].iter().map(|&d| d as i32).sum::<i32>() + half;
+            let max_center = (1 << 15) - delta_min[at + 1..=l].iter().map(|&d| d as i32).sum::<i32>() - half;
+            let center = limit(rshift_round(nlsf[at - 1] as i32 + nlsf[at] as i32, 1), min_center, max_center);
+            nlsf[at - 1] = (center - half) as i16;
+            nlsf[at] = (nlsf[at - 1] as i32 + delta_min[at] as i32) as i16;
+        }
+    }
+
+    // Fall back to sorting and clamping
+    nlsf.sort_unstable();
+    nlsf[0] = nlsf[0].max(delta_min[0]);
+    for i in 1..l {
+        nlsf[i] = nlsf[i].max(sat16(nlsf[i - 1] as i32 + delta_min[i] as i32));
+    }
+    nlsf[l - 1] = nlsf[l - 1].min(((1 << 15) - delta_min[l] as i32) as i16);
+    for i in (0..l - 1).rev() {
+        nlsf[i] = (nlsf[i] as i32).min(nlsf[i + 1] as i32 - delta_min[i + 1] as i32) as i16;
+    }
+}
+
+/// Convert NLSFs (Q15) to monic whitening filter coefficients (Q12)
+pub fn nlsf_to_lpc(a_q12: &mut [i16], nlsf: &[i16]) {
+    const QA: u32 = 16;
+    const ORDERING16: [usize; 16] = [0, 15, 8, 7, 4, 11, 12, 3, 2, 13, 10, 5, 6, 9, 14, 1];
+    const ORDERING10: [usize; 10] = [0, 9, 6, 3, 4, 5, 8, 1, 2, 7];
+    let d = nlsf.len();
+    let ordering: &[usize] = if d == 16 { &ORDERING16 } else { &ORDERING10 };
+
+    // 2 * cos(LSF) by piecewise linear interpolation
+    let mut cos_lsf = [0i32; MAX_LPC_ORDER];
+    for k in 0..d {
+        let f_int = (nlsf[k] >> 8) as usize;
+        let f_frac = (nlsf[k] as i32) - ((f_int as i32) << 8);
+        let cos_val = LSFCOS_TAB_FIX_Q12[f_int] as i32;
+        let delta = LSFCOS_TAB_FIX_Q12[f_int + 1] as i32 - cos_val;
+        cos_lsf[ordering[k]] = rshift_round((cos_val << 8) + delta * f_frac, 20 - QA);
+    }
+
+    let dd = d / 2;
+    let find_poly = |out: &mut [i32; MAX_LPC_ORDER / 2 + 1], c: &[i32]| {
+        out[0] = 1 << QA;
+        out[1] = -c[0];
+        for k in 1..dd {
+            let ftmp = c[2 * k] as i64;
+            out[k + 1] = (out[k - 1] << 1) - rshift_round64(ftmp * out[k] as i64, QA) as i32;
+            for n in (2..=k).rev() {
+                out[n] += out[n - 2] - rshift_round64(ftmp * out[n - 1] as i64, QA) as i32;
+            }
+            out[1] -= ftmp as i32;
+        }
+    };
+    let mut p = [0i32; MAX_LPC_ORDER / 2 + 1];
+    let mut q = [0i32; MAX_LPC_ORDER / 2 + 1];
+    find_poly(&mut p, &cos_lsf);
+    find_poly(&mut q, &cos_lsf[1..]);
+
+    let mut a32 = [0i32; MAX_LPC_ORDER];
+    for k in 0..dd {
+        let ptmp = p[k + 1] + p[k];
+        let qtmp = q[k + 1] - q[k];
+        a32[k] = -qtmp - ptmp;
+        a32[d - k - 1] = qtmp - ptmp;
+    }
+
+    lpc_fit(a_q12, &mut a32[..d], 12, QA + 1);
+    let mut i = 0;
+    while inverse_pred_gain(&a_q12[..d]) == 0 && i < MAX_LPC_STABILIZE_ITERATIONS {
+        // Too close to unstable: expand the bandwidth and measure again
+        bwexpander_32(&mut a32[..d], 65536 - (2 << i));
+        for k in 0..d {
+            a_q12[k] = rshift_round(a32[k], QA + 1 - 12) as i16;
+        }
+        i += 1;
+    }
+}
+
+/// Convert 32-bit coefficients to 16 bits without wrap-around
+fn lpc_fit(a_out: &mut [i16], a_in: &mut [i32], q_out: u32, q_in: u32) {
+    let d = a_in.len();
+    let mut i = 0;
+    while i < 10 {
+        let (idx, maxabs) = a_in.iter().enumerate().fold((0, 0), |(bi, bm), (k, &a)| {
+            let abs = a.wrapping_abs();
+            if abs > bm {
+                (k, abs)
+            } else {
+                (bi, bm)
+            }
+        });
+        let maxabs = rshift_round(maxabs, q_in - q_out);
+        if maxabs <= i16::MAX as i32 {
+            break;
+        }
+        // Reduce the magnitude of the coefficients
+        let maxabs = maxabs.min(163_838);
+        let chirp_q16 = 65470 - (((maxabs - i16::MAX as i32) << 14) / ((maxabs * (idx as i32 + 1)) >> 2));
+        bwexpander_32(a_in, chirp_q16);
+        i += 1;
+    }
+    if i == 10 {
+        // Last resort: clip
+        for k in 0..d {
+            a_out[k] = sat16(rshift_round(a_in[k], q_in - q_out));
+            a_in[k] = (a_out[k] as i32) << (q_in - q_out);
+        }
+    } else {
+        for k in 0..d {
+            a_out[k] = rshift_round(a_in[k], q_in - q_out) as i16;
+        }
+    }
+}
+
+/// Chirp (bandwidth expand) a Q12 LPC filter
+pub fn bwexpander(ar: &mut [i16], mut chirp_q16: i32) {
+    let d = ar.len();
+    let chirp_minus_one = chirp_q16 - 65536;
+    for a in &mut ar[..d - 1] {
+        *a = rshift_round(chirp_q16 * *a as i32, 16) as i16;
+        chirp_q16 += rshift_round(chirp_q16 * chirp_minus_one, 16);
+    }
+    ar[d - 1] = rshift_round(chirp_q16 * ar[d - 1] as i32, 16) as i16;
+}
+
+fn bwexpander_32(ar: &mut [i32], mut chirp_q16: i32) {
+    let d = ar.len();
+    let chirp_minus_one = chirp_q16 - 65536;
+    for a in &mut ar[..d - 1] {
+        *a = smulww(chirp_q16, *a);
+        chirp_q16 += rshift_round(chirp_q16 * chirp_minus_one, 16);
+    }
+    ar[d - 1] = smulww(chirp_q16, ar[d - 1]);
+}
+
+/// Inverse prediction gain of a Q12 filter in Q30, or 0 if it is unstable
+pub fn inverse_pred_gain(a_q12: &[i16]) -> i32 {
+    const QA: u32 = 24;
+    const A_LIMIT: i32 = 16_773_022; // 0.99975 in Q24
+    let order = a_q12.len();
+    let mut a_qa = [0i32; MAX_LPC_ORDER];
+    let mut dc_resp = 0;
+    for k in 0..order {
+        dc_resp += a_q12[k] as i32;
+        a_qa[k] = (a_q12[k] as i32) << (QA - 12);
+    }
+    // An unstable DC response needs no further analysis
+    if dc_resp >= 4096 {
+        return 0;
+    }
+
+    let mul32_frac_q = |a: i32, b: i32, q: u32| rshift_round64(a as i64 * b as i64, q) as i32;
+    let mut inv_gain_q30 = 1 << 30;
+    for k in (1..order).rev() {
+        if a_qa[k] > A_LIMIT || a_qa[k] < -A_LIMIT {
+            return 0;
+        }
+        let rc_q31 = -(a_qa[k] << (31 - QA));
+        let rc_mult1_q30 = (1 << 30) - smmul(rc_q31, rc_q31);
+        inv_gain_q30 = smmul(inv_gain_q30, rc_mult1_q30) << 2;
+        if inv_gain_q30 < MIN_INV_PRED_GAIN_Q30 {
+            return 0;
+        }
+        let mult2q = 32 - clz32(rc_mult1_q30.abs());
+        let rc_mult2 = inverse32_varq(rc_mult1_q30, mult2q + 30);
+        for n in 0..(k + 1) >> 1 {
+            let tmp1 = a_qa[n];
+            let tmp2 = a_qa[k - n - 1];
+            let v1 = rshift_round64(tmp1.saturating_sub(mul32_frac_q(tmp2, rc_q31, 31)) as i64 * rc_mult2 as i64, mult2q as u32);
+            let v2 = rshift_round64(tmp2.saturating_sub(mul32_frac_q(tmp1, rc_q31, 31)) as i64 * rc_mult2 as i64, mult2q as u32);
+            if v1 > i32::MAX as i64 || v1 < i32::MIN as i64 || v2 > i32::MAX as i64 || v2 < i32::MIN as i64 {
+                return 0;
+            }
+            a_qa[n] = v1 as i32;
+            a_qa[k - n - 1] = v2 as i32;
+        }
+    }
+    if a_qa[0] > A_LIMIT || a_qa[0] < -A_LIMIT {
+        return 0;
+    }
+    let rc_q31 = -(a_qa[0] << (31 - QA));
+    let rc_mult1_q30 = (1 << 30) - smmul(rc_q31, rc_q31);
+    inv_gain_q30 = smmul(inv_gain_q30, rc_mult1_q30) << 2;
+    if inv_gain_q30 < MIN_INV_PRED_GAIN_Q30 {
+        return 0;
+    }
+    inv_gain_q30
+}
+
+/// Whitening (MA) filter; the first `b.len()` outputs are zero
+pub fn lpc_analysis_filter(out: &mut [i16], input: &[i16], b: &[i16]) {
+    let d = b.len();
+    for ix in d..input.len() {
+        let mut acc = 0i32;
+        for (j, &coef) in b.iter().enumerate() {
+            acc = acc.wrapping_add((input[ix - 1 - j] as i32) * coef as i32);
+        }
+        let acc = ((input[ix] as i32) << 12).wrapping_sub(acc);
+        out[ix] = sat16(rshift_round(acc, 12));
+    }
+    out[..d].fill(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_nlsf_gives_stable_filter() {
+        // Evenly spaced NLSFs describe a flat spectrum
+        let nlsf: Vec<i16> = (1..=10).map(|i| (i * 32767 / 11) as i16).collect();
+        let mut a = [0i16; 10];
+        nlsf_to_lpc(&mut a, &nlsf);
+        assert!(a.iter().all(|&c| c.abs() < 512), "{a:?}");
+        assert!(inverse_pred_gain(&a) > 0);
+    }
+}
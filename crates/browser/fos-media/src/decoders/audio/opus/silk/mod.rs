@@ -0,0 +1,1244 @@
+//! SILK Decoder
+//!
+//! The linear-prediction layer of Opus (RFC 6716 section 4.2), used by the
+//! SILK-only and hybrid modes. This is a fixed-point port of the reference
+//! decoder, so its output is bit-exact: each frame's side information and
+//! excitation pulses are entropy decoded, run through the long-term (pitch)
+//! and short-term (LPC) synthesis filters, converted from mid/side to
+//! left/right and resampled from 8/12/16 kHz to 48 kHz. Lost frames are
+//! concealed by extrapolating the last pitch period, with comfort noise
+//! mixed in.
+
+mod fixed;
+mod lpc;
+mod resampler;
+mod tables;
+
+use self::fixed::*;
+use self::lpc::{bwexpander, inverse_pred_gain, lpc_analysis_filter, nlsf_to_lpc, NlsfCodebook, MAX_LPC_ORDER, NLSF_CB_NB_MB, NLSF_CB_WB};
+use self::resampler::Resampler;
+use self::tables::*;
+use super::range::RangeDecoder;
+
+const MAX_NB_SUBFR: usize = 4;
+const MAX_FRAMES_PER_PACKET: usize = 3;
+const MAX_FRAME_LENGTH: usize = 320;
+const MAX_SUB_FRAME_LENGTH: usize = 80;
+const LTP_ORDER: usize = 5;
+
+const TYPE_NO_VOICE_ACTIVITY: usize = 0;
+const TYPE_VOICED: usize = 2;
+
+// Excitation coding
+const SHELL_CODEC_FRAME_LENGTH: usize = 16;
+const MAX_NB_SHELL_BLOCKS: usize = MAX_FRAME_LENGTH / SHELL_CODEC_FRAME_LENGTH;
+const SILK_MAX_PULSES: usize = 16;
+const N_RATE_LEVELS: usize = 10;
+const QUANT_LEVEL_ADJUST_Q10: i32 = 80;
+const NLSF_QUANT_MAX_AMPLITUDE: i32 = 4;
+
+// Gain quantisation, 2 to 88 dB in 64 levels
+const N_LEVELS_QGAIN: i32 = 64;
+const MIN_DELTA_GAIN_QUANT: i32 = -4;
+const MAX_DELTA_GAIN_QUANT: i32 = 36;
+const GAIN_OFFSET: i32 = (2 * 128) / 6 + 16 * 128;
+const GAIN_INV_SCALE_Q16: i32 = (65536 * (((88 - 2) * 128) / 6)) / (N_LEVELS_QGAIN - 1);
+
+const BWE_AFTER_LOSS_Q16: i32 = 63570;
+const STEREO_INTERP_LEN_MS: usize = 8;
+
+// Concealment
+const BWE_COEF_Q16: i32 = 64881; // 0.99
+const V_PITCH_GAIN_START_MIN_Q14: i32 = 11469; // 0.7
+const V_PITCH_GAIN_START_MAX_Q14: i32 = 15565; // 0.95
+const MAX_PITCH_LAG_MS: usize = 18;
+const RAND_BUF_SIZE: usize = 128;
+const LOG2_INV_LPC_GAIN_HIGH_THRES: u32 = 3;
+const LOG2_INV_LPC_GAIN_LOW_THRES: u32 = 8;
+const PITCH_DRIFT_FAC_Q16: i32 = 655; // 0.01
+const HARM_ATT_Q15: [i32; 2] = [32440, 31130];
+const RAND_ATTENUATE_V_Q15: [i32; 2] = [31130, 26214];
+const RAND_ATTENUATE_UV_Q15: [i32; 2] = [32440, 29491];
+
+// Comfort noise
+const CNG_BUF_MASK_MAX: usize = 255;
+const CNG_GAIN_SMTH_Q16: i32 = 4634;
+const CNG_GAIN_SMTH_THRESHOLD_Q16: i32 = 46396;
+const CNG_NLSF_SMTH_Q16: i32 = 16348;
+
+/// Per-call configuration from the Opus layer
+#[derive(Debug, Clone, Copy)]
+pub struct SilkConfig {
+    /// Output channels
+    pub channels: usize,
+    /// Channels coded in the stream
+    pub stream_channels: usize,
+    /// Internal sample rate (8, 12 or 16 kHz)
+    pub internal_rate: u32,
+    /// Duration of the SILK payload, at least 10 ms
+    pub payload_ms: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CondCoding {
+    Independently,
+    IndependentlyNoLtpScaling,
+    Conditionally,
+}
+
+/// Quantisation indices of one frame's side information
+#[derive(Debug, Clone, Copy, Default)]
+struct SideInfo {
+    gains: [i32; MAX_NB_SUBFR],
+    ltp_index: [usize; MAX_NB_SUBFR],
+    nlsf: [i8; MAX_LPC_ORDER + 1],
+    lag_index: i32,
+    contour_index: usize,
+    signal_type: usize,
+    quant_offset_type: usize,
+    nlsf_interp_coef_q2: i32,
+    per_index: usize,
+    ltp_scale_index: usize,
+    seed: i32,
+}
+
+/// Dequantised parameters of one frame
+#[derive(Debug, Default)]
+struct FrameControl {
+    pitch_l: [i32; MAX_NB_SUBFR],
+    gains_q16: [i32; MAX_NB_SUBFR],
+    pred_coef_q12: [[i16; MAX_LPC_ORDER]; 2],
+    ltp_coef_q14: [i16; LTP_ORDER * MAX_NB_SUBFR],
+    ltp_scale_q14: i32,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PlcState {
+    pitch_l_q8: i32,
+    ltp_coef_q14: [i16; LTP_ORDER],
+    prev_lpc_q12: [i16; MAX_LPC_ORDER],
+    last_frame_lost: bool,
+    rand_seed: i32,
+    rand_scale_q14: i32,
+    conc_energy: i32,
+    conc_energy_shift: i32,
+    prev_ltp_scale_q14: i32,
+    prev_gain_q16: [i32; 2],
+    fs_khz: usize,
+    nb_subfr: usize,
+    subfr_length: usize,
+}
+
+#[derive(Debug, Clone)]
+struct CngState {
+    exc_buf_q14: Vec<i32>,
+    smth_nlsf_q15: [i16; MAX_LPC_ORDER],
+    synth_state: [i32; MAX_LPC_ORDER],
+    smth_gain_q16: i32,
+    rand_seed: i32,
+    fs_khz: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct StereoState {
+    pred_prev_q13: [i16; 2],
+    s_mid: [i16; 2],
+    s_side: [i16; 2],
+}
+
+/// Decoder state of one coded channel (mid or side)
+#[derive(Debug, Clone)]
+struct ChannelState {
+    prev_gain_q16: i32,
+    exc_q14: Vec<i32>,
+    s_lpc_q14_buf: [i32; MAX_LPC_ORDER],
+    out_buf: Vec<i16>,
+    lag_prev: i32,
+    last_gain_index: i32,
+    fs_khz: usize,
+    nb_subfr: usize,
+    frame_length: usize,
+    subfr_length: usize,
+    ltp_mem_length: usize,
+    lpc_order: usize,
+    prev_nlsf_q15: [i16; MAX_LPC_ORDER],
+    first_frame_after_reset: bool,
+    pitch_lag_low_bits_icdf: &'static [u8],
+    pitch_contour_icdf: &'static [u8],
+    n_frames_decoded: usize,
+    n_frames_per_packet: usize,
+    ec_prev_signal_type: usize,
+    ec_prev_lag_index: i32,
+    vad_flags: [bool; MAX_FRAMES_PER_PACKET],
+    lbrr_flag: bool,
+    lbrr_flags: [bool; MAX_FRAMES_PER_PACKET],
+    resampler: Resampler,
+    nlsf_cb: &'static NlsfCodebook,
+    indices: SideInfo,
+    loss_cnt: i32,
+    prev_signal_type: usize,
+    plc: PlcState,
+    cng: CngState,
+}
+
+impl ChannelState {
+    fn new() -> Self {
+        let mut state = Self {
+            prev_gain_q16: 65536,
+            exc_q14: vec![0; MAX_FRAME_LENGTH],
+            s_lpc_q14_buf: [0; MAX_LPC_ORDER],
+            out_buf: vec![0; MAX_FRAME_LENGTH + 2 * MAX_SUB_FRAME_LENGTH],
+            lag_prev: 0,
+            last_gain_index: 0,
+            fs_khz: 0,
+            nb_subfr: 0,
+            frame_length: 0,
+            subfr_length: 0,
+            ltp_mem_length: 0,
+            lpc_order: 0,
+            prev_nlsf_q15: [0; MAX_LPC_ORDER],
+            first_frame_after_reset: true,
+            pitch_lag_low_bits_icdf: &UNIFORM4_ICDF,
+            pitch_contour_icdf: &PITCH_CONTOUR_NB_ICDF,
+            n_frames_decoded: 0,
+            n_frames_per_packet: 0,
+            ec_prev_signal_type: 0,
+            ec_prev_lag_index: 0,
+            vad_flags: [false; MAX_FRAMES_PER_PACKET],
+            lbrr_flag: false,
+            lbrr_flags: [false; MAX_FRAMES_PER_PACKET],
+            resampler: Resampler::default(),
+            nlsf_cb: &NLSF_CB_NB_MB,
+            indices: SideInfo::default(),
+            loss_cnt: 0,
+            prev_signal_type: TYPE_NO_VOICE_ACTIVITY,
+            plc: PlcState::default(),
+            cng: CngState {
+                exc_buf_q14: vec![0; MAX_FRAME_LENGTH],
+                smth_nlsf_q15: [0; MAX_LPC_ORDER],
+                synth_state: [0; MAX_LPC_ORDER],
+                smth_gain_q16: 0,
+                rand_seed: 0,
+                fs_khz: 0,
+            },
+        };
+        state.cng_reset();
+        state.plc_reset();
+        state
+    }
+
+    /// Switch the internal sample rate and frame duration
+    fn set_fs(&mut self, fs_khz: usize) {
+        self.subfr_length = 5 * fs_khz;
+        let frame_length = self.nb_subfr * self.subfr_length;
+        if self.fs_khz != fs_khz {
+            self.resampler = Resampler::new(fs_khz as i32 * 1000);
+        }
+        if self.fs_khz != fs_khz || frame_length != self.frame_length {
+            self.pitch_contour_icdf = match (fs_khz, self.nb_subfr) {
+                (8, MAX_NB_SUBFR) => &PITCH_CONTOUR_NB_ICDF,
+                (8, _) => &PITCH_CONTOUR_10_MS_NB_ICDF,
+                (_, MAX_NB_SUBFR) => &PITCH_CONTOUR_ICDF,
+                _ => &PITCH_CONTOUR_10_MS_ICDF,
+            };
+            if self.fs_khz != fs_khz {
+                self.ltp_mem_length = 20 * fs_khz;
+                if fs_khz == 16 {
+                    self.lpc_order = 16;
+                    self.nlsf_cb = &NLSF_CB_WB;
+                    self.pitch_lag_low_bits_icdf = &UNIFORM8_ICDF;
+                } else {
+                    self.lpc_order = 10;
+                    self.nlsf_cb = &NLSF_CB_NB_MB;
+                    self.pitch_lag_low_bits_icdf = if fs_khz == 12 { &UNIFORM6_ICDF } else { &UNIFORM4_ICDF };
+                }
+                self.first_frame_after_reset = true;
+                self.lag_prev = 100;
+                self.last_gain_index = 10;
+                self.prev_signal_type = TYPE_NO_VOICE_ACTIVITY;
+                self.out_buf.fill(0);
+                self.s_lpc_q14_buf = [0; MAX_LPC_ORDER];
+            }
+            self.fs_khz = fs_khz;
+            self.frame_length = frame_length;
+        }
+    }
+
+    /// Decode one frame into `out`, returning its length
+    fn decode_frame(&mut self, dec: &mut RangeDecoder, out: &mut [i16], lost: bool, cond: CondCoding) -> usize {
+        let len = self.frame_length;
+        let mut ctrl = FrameControl::default();
+        if !lost {
+            let mut pulses = [0i16; MAX_FRAME_LENGTH];
+            self.decode_indices(dec, self.n_frames_decoded, false, cond);
+            decode_pulses(dec, &mut pulses, self.indices.signal_type, self.indices.quant_offset_type, len);
+            self.decode_parameters(&mut ctrl, cond);
+            self.decode_core(&mut ctrl, out, &pulses);
+            self.plc(&mut ctrl, out, false);
+            self.loss_cnt = 0;
+            self.prev_signal_type = self.indices.signal_type;
+            self.first_frame_after_reset = false;
+        } else {
+            self.plc(&mut ctrl, out, true);
+        }
+
+        // The output history feeds the LTP analysis of the next frames
+        let mv_len = self.ltp_mem_length - len;
+        self.out_buf.copy_within(len..len + mv_len, 0);
+        self.out_buf[mv_len..mv_len + len].copy_from_slice(&out[..len]);
+
+        self.cng(&ctrl, &mut out[..len]);
+        self.plc_glue_frames(&mut out[..len]);
+        self.lag_prev = ctrl.pitch_l[self.nb_subfr - 1];
+        len
+    }
+
+    fn decode_indices(&mut self, dec: &mut RangeDecoder, frame_index: usize, decode_lbrr: bool, cond: CondCoding) {
+        let ind = &mut self.indices;
+        let ix = if decode_lbrr || self.vad_flags[frame_index] {
+            dec.icdf(&TYPE_OFFSET_VAD_ICDF, 8) + 2
+        } else {
+            dec.icdf(&TYPE_OFFSET_NO_VAD_ICDF, 8)
+        };
+        ind.signal_type = ix >> 1;
+        ind.quant_offset_type = ix & 1;
+
+        // Subframe gains, the first one relative to the previous frame if conditional
+        if cond == CondCoding::Conditionally {
+            ind.gains[0] = dec.icdf(&DELTA_GAIN_ICDF, 8) as i32;
+        } else {
+            ind.gains[0] = (dec.icdf(&GAIN_ICDF[ind.signal_type], 8) << 3) as i32;
+            ind.gains[0] += dec.icdf(&UNIFORM8_ICDF, 8) as i32;
+        }
+        for i in 1..self.nb_subfr {
+            ind.gains[i] = dec.icdf(&DELTA_GAIN_ICDF, 8) as i32;
+        }
+
+        // NLSF codebook path
+        let cb = self.nlsf_cb;
+        ind.nlsf[0] = dec.icdf(&cb.cb1_icdf[(ind.signal_type >> 1) * cb.n_vectors..], 8) as i8;
+        let (ec_ix, _) = cb.unpack(ind.nlsf[0] as usize);
+        for (i, &ec) in ec_ix.iter().enumerate().take(cb.order) {
+            let mut ix = dec.icdf(&cb.ec_icdf[ec..], 8) as i32;
+            if ix == 0 {
+                ix -= dec.icdf(&NLSF_EXT_ICDF, 8) as i32;
+            } else if ix == 2 * NLSF_QUANT_MAX_AMPLITUDE {
+                ix += dec.icdf(&NLSF_EXT_ICDF, 8) as i32;
+            }
+            ind.nlsf[i + 1] = (ix - NLSF_QUANT_MAX_AMPLITUDE) as i8;
+        }
+        ind.nlsf_interp_coef_q2 = if self.nb_subfr == MAX_NB_SUBFR {
+            dec.icdf(&NLSF_INTERPOLATION_FACTOR_ICDF, 8) as i32
+        } else {
+            4
+        };
+
+        if ind.signal_type == TYPE_VOICED {
+            // Pitch lag, delta coded against the previous frame when possible
+            let mut absolute = true;
+            if cond == CondCoding::Conditionally && self.ec_prev_signal_type == TYPE_VOICED {
+                let delta = dec.icdf(&PITCH_DELTA_ICDF, 8) as i32;
+                if delta > 0 {
+                    ind.lag_index = self.ec_prev_lag_index + delta - 9;
+                    absolute = false;
+                }
+            }
+            if absolute {
+                ind.lag_index = dec.icdf(&PITCH_LAG_ICDF, 8) as i32 * (self.fs_khz as i32 >> 1);
+                ind.lag_index += dec.icdf(self.pitch_lag_low_bits_icdf, 8) as i32;
+            }
+            self.ec_prev_lag_index = ind.lag_index;
+            ind.contour_index = dec.icdf(self.pitch_contour_icdf, 8);
+
+            // LTP filter
+            ind.per_index = dec.icdf(&LTP_PER_INDEX_ICDF, 8);
+            let icdf: &[u8] = match ind.per_index {
+                0 => &LTP_GAIN_ICDF_0,
+                1 => &LTP_GAIN_ICDF_1,
+                _ => &LTP_GAIN_ICDF_2,
+            };
+            for k in 0..self.nb_subfr {
+                ind.ltp_index[k] = dec.icdf(icdf, 8);
+            }
+            ind.ltp_scale_index = if cond == CondCoding::Independently { dec.icdf(&LTPSCALE_ICDF, 8) } else { 0 };
+        }
+        self.ec_prev_signal_type = ind.signal_type;
+        ind.seed = dec.icdf(&UNIFORM4_ICDF, 8) as i32;
+    }
+
+    fn decode_parameters(&mut self, ctrl: &mut FrameControl, cond: CondCoding) {
+        let order = self.lpc_order;
+        let nb_subfr = self.nb_subfr;
+        gains_dequant(&mut ctrl.gains_q16, &self.indices.gains, &mut self.last_gain_index, cond == CondCoding::Conditionally, nb_subfr);
+
+        let nlsf_q15 = self.nlsf_cb.decode(&self.indices.nlsf);
+        nlsf_to_lpc(&mut ctrl.pred_coef_q12[1][..order], &nlsf_q15[..order]);
+
+        // The first half of a 20 ms frame may use interpolated NLSFs
+        if self.first_frame_after_reset {
+            self.indices.nlsf_interp_coef_q2 = 4;
+        }
+        let interp = self.indices.nlsf_interp_coef_q2;
+        if interp < 4 {
+            let mut nlsf0_q15 = [0i16; MAX_LPC_ORDER];
+            for i in 0..order {
+                let prev = self.prev_nlsf_q15[i] as i32;
+                nlsf0_q15[i] = (prev + ((interp * (nlsf_q15[i] as i32 - prev)) >> 2)) as i16;
+            }
+            nlsf_to_lpc(&mut ctrl.pred_coef_q12[0][..order], &nlsf0_q15[..order]);
+        } else {
+            ctrl.pred_coef_q12[0] = ctrl.pred_coef_q12[1];
+        }
+        self.prev_nlsf_q15[..order].copy_from_slice(&nlsf_q15[..order]);
+
+        // After packet loss, soften the filters to avoid energy bursts
+        if self.loss_cnt != 0 {
+            bwexpander(&mut ctrl.pred_coef_q12[0][..order], BWE_AFTER_LOSS_Q16);
+            bwexpander(&mut ctrl.pred_coef_q12[1][..order], BWE_AFTER_LOSS_Q16);
+        }
+
+        if self.indices.signal_type == TYPE_VOICED {
+            ctrl.pitch_l = decode_pitch(self.indices.lag_index, self.indices.contour_index, self.fs_khz, nb_subfr);
+            let codebook: &[[i8; LTP_ORDER]] = match self.indices.per_index {
+                0 => &LTP_GAIN_VQ_0,
+                1 => &LTP_GAIN_VQ_1,
+                _ => &LTP_GAIN_VQ_2,
+            };
+            for k in 0..nb_subfr {
+                let vector = &codebook[self.indices.ltp_index[k]];
+                for (coef, &v) in ctrl.ltp_coef_q14[k * LTP_ORDER..(k + 1) * LTP_ORDER].iter_mut().zip(vector) {
+                    *coef = (v as i16) << 7;
+                }
+            }
+            ctrl.ltp_scale_q14 = LTPSCALES_TABLE_Q14[self.indices.ltp_scale_index] as i32;
+        } else {
+            ctrl.pitch_l = [0; MAX_NB_SUBFR];
+            ctrl.ltp_coef_q14 = [0; LTP_ORDER * MAX_NB_SUBFR];
+            self.indices.per_index = 0;
+            ctrl.ltp_scale_q14 = 0;
+        }
+    }
+
+    /// Excitation reconstruction and LTP/LPC synthesis
+    fn decode_core(&mut self, ctrl: &mut FrameControl, xq: &mut [i16], pulses: &[i16]) {
+        let ltp_mem = self.ltp_mem_length;
+        let subfr_length = self.subfr_length;
+        let order = self.lpc_order;
+        let mut s_ltp = vec![0i16; ltp_mem];
+        let mut s_ltp_q15 = vec![0i32; ltp_mem + self.frame_length];
+        let mut res_q14 = vec![0i32; subfr_length];
+        let mut s_lpc_q14 = vec![0i32; subfr_length + MAX_LPC_ORDER];
+
+        let offset_q10 = QUANTIZATION_OFFSETS_Q10[self.indices.signal_type >> 1][self.indices.quant_offset_type] as i32;
+        let nlsf_interpolation = self.indices.nlsf_interp_coef_q2 < 4;
+
+        // Dequantise the pulses with pseudo-random sign flips
+        let mut rand_seed = self.indices.seed;
+        for (exc, &pulse) in self.exc_q14.iter_mut().zip(&pulses[..self.frame_length]) {
+            rand_seed = rand(rand_seed);
+            *exc = (pulse as i32) << 14;
+            if *exc > 0 {
+                *exc -= QUANT_LEVEL_ADJUST_Q10 << 4;
+            } else if *exc < 0 {
+                *exc += QUANT_LEVEL_ADJUST_Q10 << 4;
+            }
+            *exc += offset_q10 << 4;
+            if rand_seed < 0 {
+                *exc = -*exc;
+            }
+            rand_seed = rand_seed.wrapping_add(pulse as i32);
+        }
+
+        s_lpc_q14[..MAX_LPC_ORDER].copy_from_slice(&self.s_lpc_q14_buf);
+        let mut s_ltp_buf_idx = ltp_mem;
+        let mut lag = 0;
+        for k in 0..self.nb_subfr {
+            let a_q12 = ctrl.pred_coef_q12[k >> 1];
+            let mut signal_type = self.indices.signal_type;
+            let gain_q16 = ctrl.gains_q16[k];
+            let gain_q10 = gain_q16 >> 6;
+            let mut inv_gain_q31 = inverse32_varq(gain_q16, 47);
+
+            // Rescale the filter state to the new gain
+            let gain_adj_q16 = if gain_q16 != self.prev_gain_q16 {
+                let adj = div32_varq(self.prev_gain_q16, gain_q16, 16);
+                for s in &mut s_lpc_q14[..MAX_LPC_ORDER] {
+                    *s = smulww(adj, *s);
+                }
+                adj
+            } else {
+                1 << 16
+            };
+            self.prev_gain_q16 = gain_q16;
+
+            // After a lost voiced frame, keep the pitch going into an unvoiced one
+            if self.loss_cnt != 0 && self.prev_signal_type == TYPE_VOICED && self.indices.signal_type != TYPE_VOICED && k < MAX_NB_SUBFR / 2 {
+                let b = &mut ctrl.ltp_coef_q14[k * LTP_ORDER..(k + 1) * LTP_ORDER];
+                b.fill(0);
+                b[LTP_ORDER / 2] = 4096; // 0.25
+                signal_type = TYPE_VOICED;
+                ctrl.pitch_l[k] = self.lag_prev;
+            }
+
+            if signal_type == TYPE_VOICED {
+                lag = ctrl.pitch_l[k] as usize;
+                // Re-whiten the output history when the LPC filter changes
+                if k == 0 || (k == 2 && nlsf_interpolation) {
+                    let start_idx = ltp_mem - lag - order - LTP_ORDER / 2;
+                    if k == 2 {
+                        self.out_buf[ltp_mem..ltp_mem + 2 * subfr_length].copy_from_slice(&xq[..2 * subfr_length]);
+                    }
+                    let from = start_idx + k * subfr_length;
+                    lpc_analysis_filter(&mut s_ltp[start_idx..], &self.out_buf[from..from + ltp_mem - start_idx], &a_q12[..order]);
+                    if k == 0 {
+                        inv_gain_q31 = smulwb(inv_gain_q31, ctrl.ltp_scale_q14) << 2;
+                    }
+                    for i in 0..lag + LTP_ORDER / 2 {
+                        s_ltp_q15[s_ltp_buf_idx - i - 1] = smulwb(inv_gain_q31, s_ltp[ltp_mem - i - 1] as i32);
+                    }
+                } else if gain_adj_q16 != 1 << 16 {
+                    for i in 0..lag + LTP_ORDER / 2 {
+                        let j = s_ltp_buf_idx - i - 1;
+                        s_ltp_q15[j] = smulww(gain_adj_q16, s_ltp_q15[j]);
+                    }
+                }
+            }
+
+            // Long-term prediction
+            let exc_q14 = &self.exc_q14[k * subfr_length..(k + 1) * subfr_length];
+            let pres_q14: &[i32] = if signal_type == TYPE_VOICED {
+                let b = &ctrl.ltp_coef_q14[k * LTP_ORDER..(k + 1) * LTP_ORDER];
+                for i in 0..subfr_length {
+                    let pred_lag = s_ltp_buf_idx - lag + LTP_ORDER / 2;
+                    let mut ltp_pred_q13 = 2;
+                    for (j, &coef) in b.iter().enumerate() {
+                        ltp_pred_q13 = smlawb(ltp_pred_q13, s_ltp_q15[pred_lag - j], coef as i32);
+                    }
+                    res_q14[i] = exc_q14[i].wrapping_add(ltp_pred_q13 << 1);
+                    s_ltp_q15[s_ltp_buf_idx] = res_q14[i] << 1;
+                    s_ltp_buf_idx += 1;
+                }
+                &res_q14
+            } else {
+                exc_q14
+            };
+
+            // Short-term prediction
+            for i in 0..subfr_length {
+                let pred_q10 = lpc_predict(&s_lpc_q14, MAX_LPC_ORDER + i, &a_q12[..order]);
+                let s = pres_q14[i].saturating_add(lshift_sat32(pred_q10, 4));
+                s_lpc_q14[MAX_LPC_ORDER + i] = s;
+                xq[k * subfr_length + i] = sat16(rshift_round(smulww(s, gain_q10), 8));
+            }
+            s_lpc_q14.copy_within(subfr_length..subfr_length + MAX_LPC_ORDER, 0);
+        }
+        self.s_lpc_q14_buf.copy_from_slice(&s_lpc_q14[..MAX_LPC_ORDER]);
+    }
+
+    fn plc_reset(&mut self) {
+        self.plc.pitch_l_q8 = (self.frame_length as i32) << 7;
+        self.plc.prev_gain_q16 = [65536; 2];
+        self.plc.subfr_length = 20;
+        self.plc.nb_subfr = 2;
+    }
+
+    fn plc(&mut self, ctrl: &mut FrameControl, frame: &mut [i16], lost: bool) {
+        if self.fs_khz != self.plc.fs_khz {
+            self.plc_reset();
+            self.plc.fs_khz = self.fs_khz;
+        }
+        if lost {
+            self.plc_conceal(ctrl, frame);
+            self.loss_cnt += 1;
+        } else {
+            self.plc_update(ctrl);
+        }
+    }
+
+    /// Remember the pitch and filters of a good frame for concealment
+    fn plc_update(&mut self, ctrl: &FrameControl) {
+        let nb_subfr = self.nb_subfr;
+        let plc = &mut self.plc;
+        self.prev_signal_type = self.indices.signal_type;
+        if self.indices.signal_type == TYPE_VOICED {
+            // The strongest LTP filter within the last pitch period
+            let mut ltp_gain_q14 = 0;
+            let mut j = 0;
+            while j * self.subfr_length < ctrl.pitch_l[nb_subfr - 1] as usize && j < nb_subfr {
+                let base = (nb_subfr - 1 - j) * LTP_ORDER;
+                let gain: i32 = ctrl.ltp_coef_q14[base..base + LTP_ORDER].iter().map(|&c| c as i32).sum();
+                if gain > ltp_gain_q14 {
+                    ltp_gain_q14 = gain;
+                    plc.pitch_l_q8 = ctrl.pitch_l[nb_subfr - 1 - j] << 8;
+                }
+                j += 1;
+            }
+            plc.ltp_coef_q14 = [0; LTP_ORDER];
+            plc.ltp_coef_q14[LTP_ORDER / 2] = ltp_gain_q14 as i16;
+
+            // Keep the periodic gain within limits
+            if ltp_gain_q14 < V_PITCH_GAIN_START_MIN_Q14 {
+                let scale_q10 = (V_PITCH_GAIN_START_MIN_Q14 << 10) / ltp_gain_q14.max(1);
+                for c in &mut plc.ltp_coef_q14 {
+                    *c = (smulbb(*c as i32, scale_q10) >> 10) as i16;
+                }
+            } else if ltp_gain_q14 > V_PITCH_GAIN_START_MAX_Q14 {
+                let scale_q14 = (V_PITCH_GAIN_START_MAX_Q14 << 14) / ltp_gain_q14.max(1);
+                for c in &mut plc.ltp_coef_q14 {
+                    *c = (smulbb(*c as i32, scale_q14) >> 14) as i16;
+                }
+            }
+        } else {
+            plc.pitch_l_q8 = ((self.fs_khz * 18) as i32) << 8;
+            plc.ltp_coef_q14 = [0; LTP_ORDER];
+        }
+        plc.prev_lpc_q12 = ctrl.pred_coef_q12[1];
+        plc.prev_ltp_scale_q14 = ctrl.ltp_scale_q14;
+        plc.prev_gain_q16 = [ctrl.gains_q16[nb_subfr - 2], ctrl.gains_q16[nb_subfr - 1]];
+        plc.subfr_length = self.subfr_length;
+        plc.nb_subfr = nb_subfr;
+    }
+
+    /// Extrapolate a lost frame from the previous pitch period and noise
+    fn plc_conceal(&mut self, ctrl: &mut FrameControl, frame: &mut [i16]) {
+        let ltp_mem = self.ltp_mem_length;
+        let frame_length = self.frame_length;
+        let subfr_length = self.subfr_length;
+        let order = self.lpc_order;
+        let mut s_ltp = vec![0i16; ltp_mem];
+        let mut s_ltp_q14 = vec![0i32; ltp_mem + frame_length];
+        let prev_gain_q10 = [self.plc.prev_gain_q16[0] >> 6, self.plc.prev_gain_q16[1] >> 6];
+
+        if self.first_frame_after_reset {
+            self.plc.prev_lpc_q12 = [0; MAX_LPC_ORDER];
+        }
+
+        // Take the noise from the quieter of the last two subframes
+        let mut exc_buf = vec![0i16; 2 * subfr_length];
+        for k in 0..2 {
+            for i in 0..subfr_length {
+                let exc = self.exc_q14[i + (k + self.nb_subfr - 2) * subfr_length];
+                exc_buf[k * subfr_length + i] = sat16(smulww(exc, prev_gain_q10[k]) >> 8);
+            }
+        }
+        let (energy1, shift1) = sum_sqr_shift(&exc_buf[..subfr_length]);
+        let (energy2, shift2) = sum_sqr_shift(&exc_buf[subfr_length..]);
+        let plc_subfrs = if energy1 >> shift2 < energy2 >> shift1 { self.plc.nb_subfr - 1 } else { self.plc.nb_subfr };
+        let rand_base = (plc_subfrs * self.plc.subfr_length).saturating_sub(RAND_BUF_SIZE);
+
+        let mut b_q14 = self.plc.ltp_coef_q14;
+        let mut rand_scale_q14 = self.plc.rand_scale_q14;
+        let att = (self.loss_cnt as usize).min(1);
+        let harm_gain_q15 = HARM_ATT_Q15[att];
+        let mut rand_gain_q15 =
+            if self.prev_signal_type == TYPE_VOICED { RAND_ATTENUATE_V_Q15[att] } else { RAND_ATTENUATE_UV_Q15[att] };
+
+        bwexpander(&mut self.plc.prev_lpc_q12[..order], BWE_COEF_Q16);
+        let a_q12 = self.plc.prev_lpc_q12;
+
+        if self.loss_cnt == 0 {
+            rand_scale_q14 = 1 << 14;
+            if self.prev_signal_type == TYPE_VOICED {
+                for &b in &b_q14 {
+                    rand_scale_q14 -= b as i32;
+                }
+                rand_scale_q14 = rand_scale_q14.max(3277); // 0.2
+                rand_scale_q14 = smulbb(rand_scale_q14, self.plc.prev_ltp_scale_q14) >> 14;
+            } else {
+                // Reduce the noise for highly predictive filters
+                let inv_gain_q30 = inverse_pred_gain(&a_q12[..order]);
+                let down_scale_q30 = inv_gain_q30
+                    .clamp((1 << 30) >> LOG2_INV_LPC_GAIN_LOW_THRES, (1 << 30) >> LOG2_INV_LPC_GAIN_HIGH_THRES)
+                    << LOG2_INV_LPC_GAIN_HIGH_THRES;
+                rand_gain_q15 = smulwb(down_scale_q30, rand_gain_q15) >> 14;
+            }
+        }
+
+        let mut rand_seed = self.plc.rand_seed;
+        let mut lag = rshift_round(self.plc.pitch_l_q8, 8) as usize;
+        let mut s_ltp_buf_idx = ltp_mem;
+
+        // Rewhiten the output history
+        let idx = ltp_mem - lag - order - LTP_ORDER / 2;
+        lpc_analysis_filter(&mut s_ltp[idx..], &self.out_buf[idx..ltp_mem], &a_q12[..order]);
+        let inv_gain_q30 = inverse32_varq(self.plc.prev_gain_q16[1], 46).min(i32::MAX >> 1);
+        for i in idx + order..ltp_mem {
+            s_ltp_q14[i] = smulwb(inv_gain_q30, s_ltp[i] as i32);
+        }
+
+        // Periodic excitation plus attenuating noise
+        for _ in 0..self.nb_subfr {
+            for _ in 0..subfr_length {
+                let pred_lag = s_ltp_buf_idx - lag + LTP_ORDER / 2;
+                let mut ltp_pred_q12 = 2;
+                for (j, &coef) in b_q14.iter().enumerate() {
+                    ltp_pred_q12 = smlawb(ltp_pred_q12, s_ltp_q14[pred_lag - j], coef as i32);
+                }
+                rand_seed = rand(rand_seed);
+                let noise = self.exc_q14[rand_base + ((rand_seed >> 25) as usize & (RAND_BUF_SIZE - 1))];
+                s_ltp_q14[s_ltp_buf_idx] = smlawb(ltp_pred_q12, noise, rand_scale_q14) << 2;
+                s_ltp_buf_idx += 1;
+            }
+            for b in &mut b_q14 {
+                *b = (smulbb(harm_gain_q15, *b as i32) >> 15) as i16;
+            }
+            rand_scale_q14 = smulbb(rand_scale_q14, rand_gain_q15) >> 15;
+
+            // Slowly drift the pitch down
+            self.plc.pitch_l_q8 = smlawb(self.plc.pitch_l_q8, self.plc.pitch_l_q8, PITCH_DRIFT_FAC_Q16);
+            self.plc.pitch_l_q8 = self.plc.pitch_l_q8.min(((MAX_PITCH_LAG_MS * self.fs_khz) as i32) << 8);
+            lag = rshift_round(self.plc.pitch_l_q8, 8) as usize;
+        }
+
+        // LPC synthesis
+        let s_lpc_q14 = &mut s_ltp_q14[ltp_mem - MAX_LPC_ORDER..];
+        s_lpc_q14[..MAX_LPC_ORDER].copy_from_slice(&self.s_lpc_q14_buf);
+        for i in 0..frame_length {
+            let pred_q10 = lpc_predict(s_lpc_q14, MAX_LPC_ORDER + i, &a_q12[..order]);
+            let s = s_lpc_q14[MAX_LPC_ORDER + i].saturating_add(lshift_sat32(pred_q10, 4));
+            s_lpc_q14[MAX_LPC_ORDER + i] = s;
+            frame[i] = sat16(rshift_round(smulww(s, prev_gain_q10[1]), 8));
+        }
+        self.s_lpc_q14_buf.copy_from_slice(&s_lpc_q14[frame_length..frame_length + MAX_LPC_ORDER]);
+
+        self.plc.rand_seed = rand_seed;
+        self.plc.rand_scale_q14 = rand_scale_q14;
+        self.plc.ltp_coef_q14 = b_q14;
+        ctrl.pitch_l = [lag as i32; MAX_NB_SUBFR];
+    }
+
+    /// Fade in the first good frame after a loss if it is louder than the
+    /// concealment
+    fn plc_glue_frames(&mut self, frame: &mut [i16]) {
+        let plc = &mut self.plc;
+        if self.loss_cnt != 0 {
+            (plc.conc_energy, plc.conc_energy_shift) = sum_sqr_shift(frame);
+            plc.last_frame_lost = true;
+            return;
+        }
+        if plc.last_frame_lost {
+            let (mut energy, energy_shift) = sum_sqr_shift(frame);
+            if energy_shift > plc.conc_energy_shift {
+                plc.conc_energy >>= energy_shift - plc.conc_energy_shift;
+            } else if energy_shift < plc.conc_energy_shift {
+                energy >>= plc.conc_energy_shift - energy_shift;
+            }
+            if energy > plc.conc_energy {
+                let lz = clz32(plc.conc_energy) - 1;
+                plc.conc_energy <<= lz;
+                energy >>= (24 - lz).max(0);
+                let frac_q24 = plc.conc_energy / energy.max(1);
+                let mut gain_q16 = sqrt_approx(frac_q24) << 4;
+                let slope_q16 = (((1 << 16) - gain_q16) / frame.len() as i32) << 2;
+                for s in frame.iter_mut() {
+                    *s = smulwb(gain_q16, *s as i32) as i16;
+                    gain_q16 += slope_q16;
+                    if gain_q16 > 1 << 16 {
+                        break;
+                    }
+                }
+            }
+        }
+        plc.last_frame_lost = false;
+    }
+
+    fn cng_reset(&mut self) {
+        let step_q15 = i16::MAX as i32 / (self.lpc_order as i32 + 1);
+        for (i, nlsf) in self.cng.smth_nlsf_q15[..self.lpc_order].iter_mut().enumerate() {
+            *nlsf = (step_q15 * (i as i32 + 1)) as i16;
+        }
+        self.cng.smth_gain_q16 = 0;
+        self.cng.rand_seed = 3_176_576;
+    }
+
+    /// Track the background noise during inactive frames and add comfort
+    /// noise to concealed ones
+    fn cng(&mut self, ctrl: &FrameControl, frame: &mut [i16]) {
+        if self.fs_khz != self.cng.fs_khz {
+            self.cng_reset();
+            self.cng.fs_khz = self.fs_khz;
+        }
+        let order = self.lpc_order;
+        let subfr_length = self.subfr_length;
+        let cng = &mut self.cng;
+        if self.loss_cnt == 0 && self.prev_signal_type == TYPE_NO_VOICE_ACTIVITY {
+            for (smth, &prev) in cng.smth_nlsf_q15[..order].iter_mut().zip(&self.prev_nlsf_q15) {
+                *smth = (*smth as i32 + smulwb(prev as i32 - *smth as i32, CNG_NLSF_SMTH_Q16)) as i16;
+            }
+            // Keep the excitation of the loudest subframe
+            let mut max_gain_q16 = 0;
+            let mut subfr = 0;
+            for (i, &gain) in ctrl.gains_q16[..self.nb_subfr].iter().enumerate() {
+                if gain > max_gain_q16 {
+                    max_gain_q16 = gain;
+                    subfr = i;
+                }
+            }
+            cng.exc_buf_q14.copy_within(..(self.nb_subfr - 1) * subfr_length, subfr_length);
+            cng.exc_buf_q14[..subfr_length].copy_from_slice(&self.exc_q14[subfr * subfr_length..(subfr + 1) * subfr_length]);
+            for &gain in &ctrl.gains_q16[..self.nb_subfr] {
+                cng.smth_gain_q16 += smulwb(gain - cng.smth_gain_q16, CNG_GAIN_SMTH_Q16);
+                if smulww(cng.smth_gain_q16, CNG_GAIN_SMTH_THRESHOLD_Q16) > gain {
+                    cng.smth_gain_q16 = gain;
+                }
+            }
+        }
+
+        if self.loss_cnt == 0 {
+            cng.synth_state[..order].fill(0);
+            return;
+        }
+        let len = frame.len();
+        let mut sig_q14 = vec![0i32; len + MAX_LPC_ORDER];
+
+        // Noise gain: what the concealment lacks relative to the background
+        let mut gain_q16 = smulww(self.plc.rand_scale_q14, self.plc.prev_gain_q16[1]);
+        if gain_q16 >= 1 << 21 || cng.smth_gain_q16 > 1 << 23 {
+            gain_q16 = smultt(gain_q16, gain_q16);
+            gain_q16 = smultt(cng.smth_gain_q16, cng.smth_gain_q16).wrapping_sub(gain_q16 << 5);
+            gain_q16 = sqrt_approx(gain_q16) << 16;
+        } else {
+            gain_q16 = smulww(gain_q16, gain_q16);
+            gain_q16 = smulww(cng.smth_gain_q16, cng.smth_gain_q16).wrapping_sub(gain_q16 << 5);
+            gain_q16 = sqrt_approx(gain_q16) << 8;
+        }
+        let gain_q10 = gain_q16 >> 6;
+
+        let mut exc_mask = CNG_BUF_MASK_MAX;
+        while exc_mask > len {
+            exc_mask >>= 1;
+        }
+        let mut seed = cng.rand_seed;
+        for s in &mut sig_q14[MAX_LPC_ORDER..] {
+            seed = rand(seed);
+            *s = cng.exc_buf_q14[(seed >> 24) as usize & exc_mask];
+        }
+        cng.rand_seed = seed;
+
+        let mut a_q12 = [0i16; MAX_LPC_ORDER];
+        nlsf_to_lpc(&mut a_q12[..order], &cng.smth_nlsf_q15[..order]);
+        sig_q14[..MAX_LPC_ORDER].copy_from_slice(&cng.synth_state);
+        for i in 0..len {
+            let pred_q10 = lpc_predict(&sig_q14, MAX_LPC_ORDER + i, &a_q12[..order]);
+            let s = sig_q14[MAX_LPC_ORDER + i].saturating_add(lshift_sat32(pred_q10, 4));
+            sig_q14[MAX_LPC_ORDER + i] = s;
+            frame[i] = frame[i].saturating_add(sat16(rshift_round(smulww(s, gain_q10), 8)));
+        }
+        cng.synth_state.copy_from_slice(&sig_q14[len..len + MAX_LPC_ORDER]);
+    }
+}
+
+/// Short-term prediction (Q10) of sample `at` from the Q14 samples before it
+#[inline]
+fn lpc_predict(s_q14: &[i32], at: usize, a_q12: &[i16]) -> i32 {
+    let mut pred = (a_q12.len() >> 1) as i32;
+    for (j, &a) in a_q12.iter().enumerate() {
+        pred = smlawb(pred, s_q14[at - 1 - j], a as i32);
+    }
+    pred
+}
+
+fn gains_dequant(gains_q16: &mut [i32; MAX_NB_SUBFR], ind: &[i32; MAX_NB_SUBFR], prev_ind: &mut i32, conditional: bool, nb_subfr: usize) {
+    for k in 0..nb_subfr {
+        if k == 0 && !conditional {
+            // Gains may drop by at most 16 steps between frames
+            *prev_ind = ind[k].max(*prev_ind - 16);
+        } else {
+            // Large deltas are coded with double step size
+            let ind_tmp = ind[k] + MIN_DELTA_GAIN_QUANT;
+            let threshold = 2 * MAX_DELTA_GAIN_QUANT - N_LEVELS_QGAIN + *prev_ind;
+            if ind_tmp > threshold {
+                *prev_ind += (ind_tmp << 1) - threshold;
+            } else {
+                *prev_ind += ind_tmp;
+            }
+        }
+        *prev_ind = (*prev_ind).clamp(0, N_LEVELS_QGAIN - 1);
+        gains_q16[k] = log2lin((smulwb(GAIN_INV_SCALE_Q16, *prev_ind) + GAIN_OFFSET).min(3967));
+    }
+}
+
+/// Per-subframe pitch lags from the lag index and contour codebook
+fn decode_pitch(lag_index: i32, contour_index: usize, fs_khz: usize, nb_subfr: usize) -> [i32; MAX_NB_SUBFR] {
+    let min_lag = 2 * fs_khz as i32;
+    let max_lag = 18 * fs_khz as i32;
+    let lag = min_lag + lag_index;
+    let mut pitch_l = [0; MAX_NB_SUBFR];
+    for (k, p) in pitch_l[..nb_subfr].iter_mut().enumerate() {
+        let offset = match (fs_khz, nb_subfr) {
+            (8, MAX_NB_SUBFR) => CB_LAGS_STAGE2[k][contour_index],
+            (8, _) => CB_LAGS_STAGE2_10_MS[k][contour_index],
+            (_, MAX_NB_SUBFR) => CB_LAGS_STAGE3[k][contour_index],
+            _ => CB_LAGS_STAGE3_10_MS[k][contour_index],
+        };
+        *p = (lag + offset as i32).clamp(min_lag, max_lag);
+    }
+    pitch_l
+}
+
+/// Excitation pulses: rate level, pulse counts per 16-sample block, shell
+/// coded positions, LSBs and signs
+fn decode_pulses(dec: &mut RangeDecoder, pulses: &mut [i16], signal_type: usize, quant_offset_type: usize, frame_length: usize) {
+    let rate_level = dec.icdf(&RATE_LEVELS_ICDF[signal_type >> 1], 8);
+    // 10 ms at 12 kHz has a partial last block
+    let blocks = frame_length.div_ceil(SHELL_CODEC_FRAME_LENGTH);
+
+    let mut sum_pulses = [0usize; MAX_NB_SHELL_BLOCKS];
+    let mut n_lshifts = [0usize; MAX_NB_SHELL_BLOCKS];
+    for i in 0..blocks {
+        sum_pulses[i] = dec.icdf(&PULSES_PER_BLOCK_ICDF[rate_level], 8);
+        while sum_pulses[i] == SILK_MAX_PULSES + 1 {
+            n_lshifts[i] += 1;
+            // After 10 extra LSBs, the escape symbol is no longer allowed
+            let icdf = &PULSES_PER_BLOCK_ICDF[N_RATE_LEVELS - 1][(n_lshifts[i] == 10) as usize..];
+            sum_pulses[i] = dec.icdf(icdf, 8);
+        }
+    }
+
+    for (i, block) in pulses[..blocks * SHELL_CODEC_FRAME_LENGTH].chunks_exact_mut(SHELL_CODEC_FRAME_LENGTH).enumerate() {
+        shell_decode(dec, block, sum_pulses[i]);
+    }
+
+    for (i, block) in pulses[..blocks * SHELL_CODEC_FRAME_LENGTH].chunks_exact_mut(SHELL_CODEC_FRAME_LENGTH).enumerate() {
+        if n_lshifts[i] > 0 {
+            for q in block.iter_mut() {
+                let mut abs_q = *q as i32;
+                for _ in 0..n_lshifts[i] {
+                    abs_q = (abs_q << 1) + dec.icdf(&LSB_ICDF, 8) as i32;
+                }
+                *q = abs_q as i16;
+            }
+            sum_pulses[i] |= n_lshifts[i] << 5;
+        }
+    }
+
+    // Signs, with probabilities depending on the pulse count
+    let sign_icdf = &SIGN_ICDF[7 * (quant_offset_type + (signal_type << 1))..];
+    let sign_blocks = (frame_length + SHELL_CODEC_FRAME_LENGTH / 2) / SHELL_CODEC_FRAME_LENGTH;
+    for (i, block) in pulses[..sign_blocks * SHELL_CODEC_FRAME_LENGTH].chunks_exact_mut(SHELL_CODEC_FRAME_LENGTH).enumerate() {
+        let p = sum_pulses[i];
+        if p > 0 {
+            let icdf = [sign_icdf[(p & 0x1f).min(6)], 0];
+            for q in block.iter_mut().filter(|q| **q > 0) {
+                *q *= (dec.icdf(&icdf, 8) as i16) * 2 - 1;
+            }
+        }
+    }
+}
+
+/// Recursively split a pulse count between the two halves of a block
+fn shell_decode(dec: &mut RangeDecoder, out: &mut [i16], pulses: usize) {
+    if out.len() == 1 {
+        out[0] = pulses as i16;
+        return;
+    }
+    let table: &[u8] = match out.len() {
+        16 => &SHELL_CODE_TABLE3,
+        8 => &SHELL_CODE_TABLE2,
+        4 => &SHELL_CODE_TABLE1,
+        _ => &SHELL_CODE_TABLE0,
+    };
+    let left = if pulses > 0 { dec.icdf(&table[SHELL_CODE_TABLE_OFFSETS[pulses] as usize..], 8) } else { 0 };
+    let (first, second) = out.split_at_mut(out.len() / 2);
+    shell_decode(dec, first, left);
+    shell_decode(dec, second, pulses - left);
+}
+
+/// Mid/side prediction weights (Q13)
+fn stereo_decode_pred(dec: &mut RangeDecoder) -> [i32; 2] {
+    let n = dec.icdf(&STEREO_PRED_JOINT_ICDF, 8);
+    let mut ix = [[0usize; 3]; 2];
+    ix[0][2] = n / 5;
+    ix[1][2] = n - 5 * ix[0][2];
+    for row in &mut ix {
+        row[0] = dec.icdf(&UNIFORM3_ICDF, 8);
+        row[1] = dec.icdf(&UNIFORM5_ICDF, 8);
+    }
+    let mut pred_q13 = [0; 2];
+    for (pred, row) in pred_q13.iter_mut().zip(&ix) {
+        let index = row[0] + 3 * row[2];
+        let low_q13 = STEREO_PRED_QUANT_Q13[index] as i32;
+        let step_q13 = smulwb(STEREO_PRED_QUANT_Q13[index + 1] as i32 - low_q13, 6554); // 0.5 / 5 in Q16
+        *pred = smlabb(low_q13, step_q13, 2 * row[1] as i32 + 1);
+    }
+    pred_q13[0] -= pred_q13[1];
+    pred_q13
+}
+
+/// Convert mid/side to left/right in place. Both buffers hold two samples
+/// of history followed by the frame, and the output is delayed by one
+/// sample.
+fn stereo_ms_to_lr(state: &mut StereoState, x1: &mut [i16], x2: &mut [i16], pred_q13: [i32; 2], fs_khz: usize, frame_length: usize) {
+    x1[..2].copy_from_slice(&state.s_mid);
+    x2[..2].copy_from_slice(&state.s_side);
+    state.s_mid.copy_from_slice(&x1[frame_length..frame_length + 2]);
+    state.s_side.copy_from_slice(&x2[frame_length..frame_length + 2]);
+
+    // Interpolate the predictors over the first 8 ms
+    let interp_len = STEREO_INTERP_LEN_MS * fs_khz;
+    let mut pred0_q13 = state.pred_prev_q13[0] as i32;
+    let mut pred1_q13 = state.pred_prev_q13[1] as i32;
+    let denom_q16 = (1 << 16) / interp_len as i32;
+    let delta0_q13 = rshift_round(smulbb(pred_q13[0] - pred0_q13, denom_q16), 16);
+    let delta1_q13 = rshift_round(smulbb(pred_q13[1] - pred1_q13, denom_q16), 16);
+    for n in 0..frame_length {
+        if n < interp_len {
+            pred0_q13 += delta0_q13;
+            pred1_q13 += delta1_q13;
+        } else {
+            pred0_q13 = pred_q13[0];
+            pred1_q13 = pred_q13[1];
+        }
+        let sum = ((x1[n] as i32 + x1[n + 2] as i32) + ((x1[n + 1] as i32) << 1)) << 9;
+        let sum = smlawb((x2[n + 1] as i32) << 8, sum, pred0_q13);
+        let sum = smlawb(sum, (x1[n + 1] as i32) << 11, pred1_q13);
+        x2[n + 1] = sat16(rshift_round(sum, 8));
+    }
+    state.pred_prev_q13 = [pred_q13[0] as i16, pred_q13[1] as i16];
+
+    for n in 1..=frame_length {
+        let (mid, side) = (x1[n] as i32, x2[n] as i32);
+        x1[n] = sat16(mid + side);
+        x2[n] = sat16(mid - side);
+    }
+}
+
+/// SILK decoder for one Opus stream (up to two coded channels)
+#[derive(Debug, Clone)]
+pub struct SilkDecoder {
+    channels: [ChannelState; 2],
+    stereo: StereoState,
+    n_channels_api: usize,
+    n_channels_internal: usize,
+    prev_decode_only_middle: bool,
+}
+
+impl Default for SilkDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SilkDecoder {
+    pub fn new() -> Self {
+        Self {
+            channels: [ChannelState::new(), ChannelState::new()],
+            stereo: StereoState::default(),
+            n_channels_api: 0,
+            n_channels_internal: 0,
+            prev_decode_only_middle: false,
+        }
+    }
+
+    /// Reset the decoding state, e.g. after CELT-only frames
+    pub fn reset(&mut self) {
+        self.channels = [ChannelState::new(), ChannelState::new()];
+        self.stereo = StereoState::default();
+        self.prev_decode_only_middle = false;
+    }
+
+    /// Decode (or conceal, if `lost`) one SILK frame into `out`, interleaved
+    /// by output channel at 48 kHz. `new_packet` marks the first frame of a
+    /// packet. Returns the number of samples per channel.
+    pub fn decode(&mut self, dec: &mut RangeDecoder, lost: bool, new_packet: bool, config: &SilkConfig, out: &mut [i16]) -> usize {
+        let n_api = config.channels;
+        let n_int = config.stream_channels;
+        if new_packet {
+            for channel in &mut self.channels[..n_int] {
+                channel.n_frames_decoded = 0;
+            }
+        }
+        // Mono to stereo transition: start the side channel from scratch
+        if n_int > self.n_channels_internal {
+            self.channels[1] = ChannelState::new();
+        }
+        let stereo_to_mono =
+            n_int == 1 && self.n_channels_internal == 2 && config.internal_rate as usize == 1000 * self.channels[0].fs_khz;
+
+        if self.channels[0].n_frames_decoded == 0 {
+            let (frames_per_packet, nb_subfr) = match config.payload_ms {
+                20 => (1, 4),
+                40 => (2, 4),
+                60 => (3, 4),
+                _ => (1, 2),
+            };
+            let fs_khz = (config.internal_rate as usize >> 10) + 1;
+            for channel in &mut self.channels[..n_int] {
+                channel.n_frames_per_packet = frames_per_packet;
+                channel.nb_subfr = nb_subfr;
+                channel.set_fs(fs_khz);
+            }
+        }
+        if n_api == 2 && n_int == 2 && (self.n_channels_api == 1 || self.n_channels_internal == 1) {
+            self.stereo.pred_prev_q13 = [0; 2];
+            self.stereo.s_side = [0; 2];
+            self.channels[1].resampler = self.channels[0].resampler.clone();
+        }
+        self.n_channels_api = n_api;
+        self.n_channels_internal = n_int;
+
+        let mut ms_pred_q13 = [0; 2];
+        let mut decode_only_middle = false;
+        if !lost && self.channels[0].n_frames_decoded == 0 {
+            // VAD and LBRR flags of all frames in the packet
+            for channel in &mut self.channels[..n_int] {
+                for flag in &mut channel.vad_flags[..channel.n_frames_per_packet] {
+                    *flag = dec.bit_logp(1);
+                }
+                channel.lbrr_flag = dec.bit_logp(1);
+            }
+            for channel in &mut self.channels[..n_int] {
+                channel.lbrr_flags = [false; MAX_FRAMES_PER_PACKET];
+                if channel.lbrr_flag {
+                    if channel.n_frames_per_packet == 1 {
+                        channel.lbrr_flags[0] = true;
+                    } else {
+                        let icdf: &[u8] = if channel.n_frames_per_packet == 2 { &LBRR_FLAGS_2_ICDF } else { &LBRR_FLAGS_3_ICDF };
+                        let symbol = dec.icdf(icdf, 8) + 1;
+                        for (i, flag) in channel.lbrr_flags[..channel.n_frames_per_packet].iter_mut().enumerate() {
+                            *flag = (symbol >> i) & 1 != 0;
+                        }
+                    }
+                }
+            }
+            // Skip the low-bitrate redundancy; it is only used for FEC
+            for i in 0..self.channels[0].n_frames_per_packet {
+                for n in 0..n_int {
+                    if !self.channels[n].lbrr_flags[i] {
+                        continue;
+                    }
+                    if n_int == 2 && n == 0 {
+                        stereo_decode_pred(dec);
+                        if !self.channels[1].lbrr_flags[i] {
+                            dec.icdf(&STEREO_ONLY_CODE_MID_ICDF, 8);
+                        }
+                    }
+                    let cond = if i > 0 && self.channels[n].lbrr_flags[i - 1] {
+                        CondCoding::Conditionally
+                    } else {
+                        CondCoding::Independently
+                    };
+                    let channel = &mut self.channels[n];
+                    channel.decode_indices(dec, i, true, cond);
+                    let mut pulses = [0i16; MAX_FRAME_LENGTH];
+                    decode_pulses(dec, &mut pulses, channel.indices.signal_type, channel.indices.quant_offset_type, channel.frame_length);
+                }
+            }
+        }
+
+        if n_int == 2 {
+            if !lost {
+                ms_pred_q13 = stereo_decode_pred(dec);
+                if !self.channels[1].vad_flags[self.channels[0].n_frames_decoded] {
+                    decode_only_middle = dec.icdf(&STEREO_ONLY_CODE_MID_ICDF, 8) == 1;
+                }
+            } else {
+                ms_pred_q13 = self.stereo.pred_prev_q13.map(i32::from);
+            }
+        }
+        // First frame with side coding after mid-only ones
+        if n_int == 2 && !decode_only_middle && self.prev_decode_only_middle {
+            let side = &mut self.channels[1];
+            side.out_buf.fill(0);
+            side.s_lpc_q14_buf = [0; MAX_LPC_ORDER];
+            side.lag_prev = 100;
+            side.last_gain_index = 10;
+            side.prev_signal_type = TYPE_NO_VOICE_ACTIVITY;
+            side.first_frame_after_reset = true;
+        }
+
+        // Decoded frames with two samples of history in front
+        let frame_length = self.channels[0].frame_length;
+        let mut samples = [vec![0i16; frame_length + 2], vec![0i16; frame_length + 2]];
+        let has_side = if lost { !self.prev_decode_only_middle } else { !decode_only_middle };
+        let mut n_samples_dec = 0;
+        let frame_index = self.channels[0].n_frames_decoded;
+        for (n, (channel, frame)) in self.channels.iter_mut().zip(&mut samples).enumerate().take(n_int) {
+            if n == 0 || has_side {
+                let cond = if frame_index == 0 {
+                    CondCoding::Independently
+                } else if n > 0 && self.prev_decode_only_middle {
+                    // The side LTP state is well defined after a skipped frame
+                    CondCoding::IndependentlyNoLtpScaling
+                } else {
+                    CondCoding::Conditionally
+                };
+                n_samples_dec = channel.decode_frame(dec, &mut frame[2..], lost, cond);
+            } else {
+                frame[2..2 + n_samples_dec].fill(0);
+            }
+            channel.n_frames_decoded += 1;
+        }
+
+        let fs_khz = self.channels[0].fs_khz;
+        if n_api == 2 && n_int == 2 {
+            let (mid, side) = samples.split_at_mut(1);
+            stereo_ms_to_lr(&mut self.stereo, &mut mid[0], &mut side[0], ms_pred_q13, fs_khz, n_samples_dec);
+        } else {
+            samples[0][..2].copy_from_slice(&self.stereo.s_mid);
+            self.stereo.s_mid.copy_from_slice(&samples[0][n_samples_dec..n_samples_dec + 2]);
+        }
+
+        // Resample to 48 kHz and interleave
+        let n_samples_out = n_samples_dec * 48 / fs_khz;
+        let mut resampled = vec![0i16; n_samples_out];
+        for n in 0..n_api.min(n_int) {
+            self.channels[n].resampler.process(&mut resampled, &samples[n][1..1 + n_samples_dec]);
+            for (i, &s) in resampled.iter().enumerate() {
+                out[n + n_api * i] = s;
+            }
+        }
+        if n_api == 2 && n_int == 1 {
+            if stereo_to_mono {
+                // Keep the right channel's resampler running after collapsing to mono
+                self.channels[1].resampler.process(&mut resampled, &samples[0][1..1 + n_samples_dec]);
+                for (i, &s) in resampled.iter().enumerate() {
+                    out[1 + 2 * i] = s;
+                }
+            } else {
+                for i in 0..n_samples_out {
+                    out[1 + 2 * i] = out[2 * i];
+                }
+            }
+        }
+
+        if lost {
+            // Don't clamp the gains to the concealed ones after a loss
+            for channel in &mut self.channels[..self.n_channels_internal] {
+                channel.last_gain_index = 10;
+            }
+        } else {
+            self.prev_decode_only_middle = decode_only_middle;
+        }
+        n_samples_out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pitch_lags_are_clamped() {
+        // Lag index 0 with the widest contour stays within 2..18 ms
+        for contour in 0..34 {
+            let lags = decode_pitch(0, contour, 16, 4);
+            assert!(lags.iter().all(|&l| (32..=288).contains(&l)), "{lags:?}");
+        }
+        let mut gains = [0; MAX_NB_SUBFR];
+        let mut prev = 10;
+        gains_dequant(&mut gains, &[63, 4, 4, 4], &mut prev, false, 4);
+        assert_eq!(prev, 63);
+        assert!(gains.iter().all(|&g| g == gains[0] && g > 1 << 28));
+    }
+
+    #[test]
+    fn test_lost_frames_conceal_silence() {
+        let mut silk = SilkDecoder::new();
+        let config = SilkConfig { channels: 2, stream_channels: 2, internal_rate: 16000, payload_ms: 20 };
+        let mut out = vec![1i16; 960 * 2];
+        let mut dec = RangeDecoder::new(&[]);
+        assert_eq!(silk.decode(&mut dec, true, true, &config, &mut out), 960);
+        assert!(out.iter().all(|&s| s == 0));
+    }
+}
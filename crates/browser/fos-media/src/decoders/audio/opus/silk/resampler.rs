@@ -0,0 +1,122 @@
+//! SILK Output Resampler
+//!
+//! Upsamples the 8/12/16 kHz SILK signal to the 48 kHz output: a 2x
+//! all-pass interpolator followed by a 12-phase fractional FIR, with the
+//! decoder delay compensation of the reference implementation.
+
+use super::fixed::*;
+use super::tables::{RESAMPLER_FRAC_FIR_12, RESAMPLER_UP2_HQ_0, RESAMPLER_UP2_HQ_1};
+
+const ORDER_FIR_12: usize = 8;
+const MAX_BATCH_SIZE_MS: usize = 10;
+
+/// State of one channel's resampler
+#[derive(Debug, Clone, Default)]
+pub struct Resampler {
+    s_iir: [i32; 6],
+    s_fir: [i16; ORDER_FIR_12],
+    delay_buf: [i16; 16],
+    input_delay: usize,
+    fs_in_khz: usize,
+    fs_out_khz: usize,
+    batch_size: usize,
+    inv_ratio_q16: i32,
+}
+
+impl Resampler {
+    /// Resampler from `fs_in` (8, 12 or 16 kHz) to 48 kHz
+    pub fn new(fs_in: i32) -> Self {
+        let fs_out = 48000;
+        // Delay compensation to equalise the total delay of all bandwidths
+        let input_delay = match fs_in {
+            12000 => 4,
+            16000 => 7,
+            _ => 0,
+        };
+        let fs_in_khz = (fs_in / 1000) as usize;
+        // The ratio is relative to the output of the 2x stage
+        let mut inv_ratio_q16 = ((fs_in << 15) / fs_out) << 2;
+        while smulww(inv_ratio_q16, fs_out) < fs_in << 1 {
+            inv_ratio_q16 += 1;
+        }
+        Self {
+            input_delay,
+            fs_in_khz,
+            fs_out_khz: (fs_out / 1000) as usize,
+            batch_size: fs_in_khz * MAX_BATCH_SIZE_MS,
+            inv_ratio_q16,
+            ..Self::default()
+        }
+    }
+
+    /// Resample `input` (at least 1 ms) into `out`
+    pub fn process(&mut self, out: &mut [i16], input: &[i16]) {
+        let n_samples = self.fs_in_khz - self.input_delay;
+        let mut delay_buf = self.delay_buf;
+        delay_buf[self.input_delay..self.fs_in_khz].copy_from_slice(&input[..n_samples]);
+        self.iir_fir(out, &delay_buf[..self.fs_in_khz]);
+        let len = input.len();
+        self.iir_fir(&mut out[self.fs_out_khz..], &input[n_samples..len - self.input_delay]);
+        self.delay_buf[..self.input_delay].copy_from_slice(&input[len - self.input_delay..]);
+    }
+
+    fn iir_fir(&mut self, out: &mut [i16], mut input: &[i16]) {
+        let mut buf = vec![0i16; 2 * self.batch_size + ORDER_FIR_12];
+        buf[..ORDER_FIR_12].copy_from_slice(&self.s_fir);
+        let mut out_at = 0;
+        let mut n_in;
+        loop {
+            n_in = input.len().min(self.batch_size);
+            up2_hq(&mut self.s_iir, &mut buf[ORDER_FIR_12..], &input[..n_in]);
+            let max_index_q16 = (n_in as i32) << 17;
+            let mut index_q16 = 0;
+            while index_q16 < max_index_q16 {
+                let table = smulwb(index_q16 & 0xffff, 12) as usize;
+                let b = &buf[(index_q16 >> 16) as usize..];
+                let fir = &RESAMPLER_FRAC_FIR_12;
+                let mut res = smulbb(b[0] as i32, fir[table][0] as i32);
+                res = smlabb(res, b[1] as i32, fir[table][1] as i32);
+                res = smlabb(res, b[2] as i32, fir[table][2] as i32);
+                res = smlabb(res, b[3] as i32, fir[table][3] as i32);
+                res = smlabb(res, b[4] as i32, fir[11 - table][3] as i32);
+                res = smlabb(res, b[5] as i32, fir[11 - table][2] as i32);
+                res = smlabb(res, b[6] as i32, fir[11 - table][1] as i32);
+                res = smlabb(res, b[7] as i32, fir[11 - table][0] as i32);
+                out[out_at] = sat16(rshift_round(res, 15));
+                out_at += 1;
+                index_q16 += self.inv_ratio_q16;
+            }
+            input = &input[n_in..];
+            if input.is_empty() {
+                break;
+            }
+            buf.copy_within(n_in << 1..(n_in << 1) + ORDER_FIR_12, 0);
+        }
+        self.s_fir.copy_from_slice(&buf[n_in << 1..(n_in << 1) + ORDER_FIR_12]);
+    }
+}
+
+/// 2x upsampler: two third-order all-pass branches for the even and odd
+/// output samples
+fn up2_hq(s: &mut [i32; 6], out: &mut [i16], input: &[i16]) {
+    let allpass = |s: &mut [i32], coefs: &[i16; 3], in32: i32| {
+        let y = in32 - s[0];
+        let x = smulwb(y, coefs[0] as i32);
+        let out1 = s[0] + x;
+        s[0] = in32 + x;
+        let y = out1 - s[1];
+        let x = smulwb(y, coefs[1] as i32);
+        let out2 = s[1] + x;
+        s[1] = out1 + x;
+        let y = out2 - s[2];
+        let x = smlawb(y, y, coefs[2] as i32);
+        let out1 = s[2] + x;
+        s[2] = out2 + x;
+        sat16(rshift_round(out1, 10))
+    };
+    for (k, &sample) in input.iter().enumerate() {
+        let in32 = (sample as i32) << 10;
+        out[2 * k] = allpass(&mut s[..3], &RESAMPLER_UP2_HQ_0, in32);
+        out[2 * k + 1] = allpass(&mut s[3..], &RESAMPLER_UP2_HQ_1, in32);
+    }
+}
@@ -1,8 +1,12 @@
 //! Vorbis Decoder
 //!
-//! Pure-Rust implementation of Vorbis audio decoding.
+//! Pure-Rust implementation of Vorbis I audio decoding: the three header
+//! packets (in-band or from Xiph-laced codec private data), Huffman/VQ
+//! codebooks, floor types 0 and 1, residue types 0 to 2, channel coupling
+//! and windowed IMDCT overlap-add.
 
-use super::{AudioDecoderState, imdct, window_sine};
+use super::AudioDecoderState;
+use super::mdct::Imdct;
 use crate::decoders::{AudioSamples, EncodedPacket, DecoderResult, DecoderError, AudioDecoderTrait};
 use std::time::Duration;
 
@@ -24,9 +28,15 @@ pub struct VorbisIdHeader {
 pub struct VorbisDecoder {
     state: AudioDecoderState,
     id_header: Option<VorbisIdHeader>,
-    window_short: Vec<f32>,
-    window_long: Vec<f32>,
+    setup: Option<Setup>,
+    /// IMDCTs for short and long blocks
+    imdct: Option<[Imdct; 2]>,
+    /// Window slopes for short and long overlaps
+    slopes: [Vec<f32>; 2],
+    /// Windowed output of the previous block, per channel
     prev_samples: Vec<Vec<f32>>,
+    /// Size of the previous block, `None` before the first audio packet
+    prev_size: Option<usize>,
 }
 
 impl VorbisDecoder {
@@ -34,16 +44,18 @@ impl VorbisDecoder {
         Self {
             state: AudioDecoderState { sample_rate: 44100, channels: 2, samples_decoded: 0 },
             id_header: None,
-            window_short: window_sine(256),
-            window_long: window_sine(2048),
+            setup: None,
+            imdct: None,
+            slopes: [Vec::new(), Vec::new()],
             prev_samples: Vec::new(),
+            prev_size: None,
         }
     }
-    
+
     fn parse_id_header(&mut self, data: &[u8]) -> DecoderResult<()> {
         if data.len() < 30 { return Err(DecoderError::NeedMoreData); }
         if data[0] != 1 || &data[1..7] != b"vorbis" { return Err(DecoderError::InvalidBitstream("Not Vorbis ID header".into())); }
-        
+
         let version = u32::from_le_bytes([data[7], data[8], data[9], data[10]]);
         let channels = data[11];
         let sample_rate = u32::from_le_bytes([data[12], data[13], data[14], data[15]]);
@@ -53,30 +65,142 @@ impl VorbisDecoder {
         let blocksizes = data[28];
         let blocksize_0 = blocksizes & 0x0F;
         let blocksize_1 = (blocksizes >> 4) & 0x0F;
-        
+        if version != 0 || channels == 0 || sample_rate == 0 || blocksize_0 < 6 || blocksize_1 > 13 || blocksize_0 > blocksize_1 || data[29] & 1 == 0 {
+            return Err(DecoderError::InvalidBitstream("Invalid Vorbis ID header".into()));
+        }
+
         self.state.sample_rate = sample_rate;
         self.state.channels = channels as u32;
-        self.prev_samples = vec![vec![0.0; 1 << blocksize_1]; channels as usize];
-        
+        self.imdct = Some([Imdct::new(1 << blocksize_0), Imdct::new(1 << blocksize_1)]);
+        self.slopes = [window_slope(1 << (blocksize_0 - 1)), window_slope(1 << (blocksize_1 - 1))];
+        self.prev_samples = vec![Vec::new(); channels as usize];
+        self.prev_size = None;
+
         self.id_header = Some(VorbisIdHeader { version, channels, sample_rate, bitrate_max, bitrate_nominal, bitrate_min, blocksize_0, blocksize_1 });
-        
+
         Ok(())
     }
-    
-    fn decode_audio_packet(&mut self, _data: &[u8], pts: Duration) -> DecoderResult<AudioSamples> {
+
+    fn parse_setup_header(&mut self, data: &[u8]) -> DecoderResult<()> {
+        let channels = self.id_header.as_ref().ok_or_else(|| DecoderError::InvalidBitstream("No ID header".into()))?.channels as usize;
+        let mut r = BitReader::new(&data[7..]);
+        self.setup = Some(Setup::parse(&mut r, channels)?);
+        Ok(())
+    }
+
+    /// Decode an audio packet; the first one only primes the overlap
+    fn decode_audio_packet(&mut self, data: &[u8], pts: Duration) -> DecoderResult<AudioSamples> {
         let header = self.id_header.as_ref().ok_or_else(|| DecoderError::InvalidBitstream("No ID header".into()))?;
-        
-        let blocksize = 1 << header.blocksize_1;
-        let samples_per_channel = blocksize / 2;
-        let total_samples = samples_per_channel * header.channels as usize;
-        
-        // Simulated decode - real impl would do codebook lookup, floor/residue decode, IMDCT
-        let samples = vec![0.0f32; total_samples];
-        
-        let duration = Duration::from_secs_f64(samples_per_channel as f64 / header.sample_rate as f64);
-        self.state.samples_decoded += samples_per_channel as u64;
-        
-        Ok(AudioSamples { pts, duration, sample_rate: header.sample_rate, channels: header.channels as u32, data: samples })
+        let setup = self.setup.as_ref().ok_or_else(|| DecoderError::InvalidBitstream("No setup header".into()))?;
+        let imdct = self.imdct.as_ref().ok_or_else(|| DecoderError::InvalidBitstream("No ID header".into()))?;
+        let channels = header.channels as usize;
+        let sizes = [1usize << header.blocksize_0, 1usize << header.blocksize_1];
+
+        let mut r = BitReader::new(data);
+        if r.read(1)? != 0 { return Err(DecoderError::InvalidBitstream("Not an audio packet".into())); }
+        let mode = setup.modes.get(r.read(ilog(setup.modes.len() as u32 - 1))? as usize)
+            .ok_or_else(|| DecoderError::InvalidBitstream("Invalid mode".into()))?;
+        let long = mode.blockflag;
+        let n = sizes[long as usize];
+        let (prev_long, next_long) = if long { (r.read(1)? == 1, r.read(1)? == 1) } else { (false, false) };
+        let mapping = &setup.mappings[mode.mapping];
+        let half = n / 2;
+
+        // Floors; end of packet leaves a channel unused
+        let floors: Vec<Option<FloorData>> = (0..channels).map(|ch| {
+            let submap = mapping.mux[ch] as usize;
+            setup.floors[mapping.submaps[submap].0].decode(&mut r, &setup.codebooks)
+        }).collect();
+
+        // Coupled channels are decoded together
+        let mut no_residue: Vec<bool> = floors.iter().map(Option::is_none).collect();
+        for &(magnitude, angle) in &mapping.coupling {
+            if !no_residue[magnitude] || !no_residue[angle] {
+                no_residue[magnitude] = false;
+                no_residue[angle] = false;
+            }
+        }
+
+        let mut spectra = vec![vec![0.0f32; half]; channels];
+        for (i, &(_, residue)) in mapping.submaps.iter().enumerate() {
+            let members: Vec<usize> = (0..channels).filter(|&ch| mapping.mux[ch] as usize == i).collect();
+            let mut vectors: Vec<Vec<f32>> = members.iter().map(|&ch| std::mem::take(&mut spectra[ch])).collect();
+            let skip: Vec<bool> = members.iter().map(|&ch| no_residue[ch]).collect();
+            setup.residues[residue].decode(&mut r, &setup.codebooks, &mut vectors, &skip);
+            for (&ch, vector) in members.iter().zip(vectors) {
+                spectra[ch] = vector;
+            }
+        }
+
+        for &(magnitude, angle) in mapping.coupling.iter().rev() {
+            let mut mags = std::mem::take(&mut spectra[magnitude]);
+            for (mag, ang) in mags[..half].iter_mut().zip(&mut spectra[angle][..half]) {
+                let (m, a) = (*mag, *ang);
+                (*mag, *ang) = if m > 0.0 {
+                    if a > 0.0 { (m, m - a) } else { (m + a, m) }
+                } else if a > 0.0 {
+                    (m, m + a)
+                } else {
+                    (m - a, m)
+                };
+            }
+            spectra[magnitude] = mags;
+        }
+
+        // Window: short overlaps at either end of a long block next to a short one
+        let short_half = sizes[0] / 2;
+        let (left_start, left_slope) = if long && !prev_long { (n / 4 - sizes[0] / 4, &self.slopes[0]) } else { (0, &self.slopes[long as usize]) };
+        let (right_start, right_slope) = if long && !next_long { (n * 3 / 4 - sizes[0] / 4, &self.slopes[0]) } else { (half, &self.slopes[long as usize]) };
+        debug_assert!(left_slope.len() == short_half || left_slope.len() == half);
+
+        let mut time = vec![0.0f32; n];
+        let mut blocks = Vec::with_capacity(channels);
+        for (ch, spectrum) in spectra.iter_mut().enumerate() {
+            match &floors[ch] {
+                Some(floor) => {
+                    let curve = setup.floors[mapping.submaps[mapping.mux[ch] as usize].0].curve(floor, half);
+                    for (s, c) in spectrum.iter_mut().zip(curve) { *s *= c; }
+                }
+                None => spectrum.fill(0.0),
+            }
+            imdct[long as usize].process(spectrum, &mut time);
+            let mut block = vec![0.0f32; n];
+            for (i, w) in left_slope.iter().enumerate() { block[left_start + i] = time[left_start + i] * w; }
+            let left_end = left_start + left_slope.len();
+            block[left_end..right_start].copy_from_slice(&time[left_end..right_start]);
+            for (i, w) in right_slope.iter().rev().enumerate() { block[right_start + i] = time[right_start + i] * w; }
+            blocks.push(block);
+        }
+
+        // Overlap-add from the previous block's center to this block's center
+        let mut out = Vec::new();
+        if let Some(prev_n) = self.prev_size {
+            let len = prev_n / 4 + n / 4;
+            // Overlap centers: 3/4 into the previous block, 1/4 into this one
+            let offset = (prev_n * 3 / 4) as isize - (n / 4) as isize;
+            out.reserve(len * channels);
+            for j in 0..len {
+                let p = prev_n / 2 + j;
+                let c = p as isize - offset;
+                for (prev, cur) in self.prev_samples.iter().zip(&blocks) {
+                    let mut v = if p < prev_n { prev[p] } else { 0.0 };
+                    if c >= 0 { v += cur[c as usize]; }
+                    out.push(v);
+                }
+            }
+        }
+        self.prev_samples = blocks;
+        self.prev_size = Some(n);
+
+        let frames = out.len() / channels;
+        let duration = Duration::from_secs_f64(frames as f64 / header.sample_rate as f64);
+        self.state.samples_decoded += frames as u64;
+
+        Ok(AudioSamples { pts, duration, sample_rate: header.sample_rate, channels: channels as u32, data: out })
+    }
+
+    fn empty(&self, pts: Duration) -> AudioSamples {
+        AudioSamples { pts, duration: Duration::ZERO, sample_rate: self.state.sample_rate, channels: self.state.channels, data: Vec::new() }
     }
 }
 
@@ -84,25 +208,791 @@ impl Default for VorbisDecoder { fn default() -> Self { Self::new() } }
 
 impl AudioDecoderTrait for VorbisDecoder {
     fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<AudioSamples> {
-        if packet.data.len() >= 7 && packet.data[0] == 1 && &packet.data[1..7] == b"vorbis" {
-            self.parse_id_header(&packet.data)?;
-            return Ok(AudioSamples { pts: packet.pts, duration: Duration::ZERO, sample_rate: self.state.sample_rate, channels: self.state.channels, data: Vec::new() });
-        }
-        if packet.data.len() >= 7 && (packet.data[0] == 3 || packet.data[0] == 5) && &packet.data[1..7] == b"vorbis" {
-            // Skip comment and setup headers
-            return Ok(AudioSamples { pts: packet.pts, duration: Duration::ZERO, sample_rate: self.state.sample_rate, channels: self.state.channels, data: Vec::new() });
+        if packet.data.len() >= 7 && &packet.data[1..7] == b"vorbis" {
+            match packet.data[0] {
+                1 => self.parse_id_header(&packet.data)?,
+                // Comments are not needed for decoding
+                3 => {}
+                5 => self.parse_setup_header(&packet.data)?,
+                _ => return Err(DecoderError::InvalidBitstream("Unknown header packet".into())),
+            }
+            return Ok(self.empty(packet.pts));
         }
         self.decode_audio_packet(&packet.data, packet.pts)
     }
     fn flush(&mut self) -> Option<AudioSamples> { None }
-    fn reset(&mut self) { self.state.samples_decoded = 0; for ch in &mut self.prev_samples { ch.fill(0.0); } }
+    fn reset(&mut self) { self.state.samples_decoded = 0; self.prev_size = None; }
     fn sample_rate(&self) -> u32 { self.state.sample_rate }
     fn channels(&self) -> u32 { self.state.channels }
+    /// Xiph-laced identification, comment and setup headers (Matroska
+    /// CodecPrivate)
+    fn configure(&mut self, extradata: &[u8]) -> DecoderResult<()> {
+        let packets = xiph_unlace(extradata).ok_or_else(|| DecoderError::InvalidBitstream("Invalid Vorbis codec private data".into()))?;
+        for data in packets {
+            self.decode(&EncodedPacket { data: data.to_vec(), pts: Duration::ZERO, dts: Duration::ZERO, is_key: true })?;
+        }
+        if self.setup.is_none() { return Err(DecoderError::InvalidBitstream("No setup header".into())); }
+        Ok(())
+    }
+}
+
+/// Split Xiph-laced packets: a count, then lace-coded sizes of all but the last
+pub(crate) fn xiph_unlace(data: &[u8]) -> Option<Vec<&[u8]>> {
+    let (&count, mut rest) = data.split_first()?;
+    let mut sizes = Vec::new();
+    for _ in 0..count {
+        let mut size = 0usize;
+        loop {
+            let (&b, tail) = rest.split_first()?;
+            rest = tail;
+            size += b as usize;
+            if b != 255 { break; }
+        }
+        sizes.push(size);
+    }
+    let mut packets = Vec::new();
+    for size in sizes {
+        if size > rest.len() { return None; }
+        let (packet, tail) = rest.split_at(size);
+        packets.push(packet);
+        rest = tail;
+    }
+    packets.push(rest);
+    Some(packets)
+}
+
+/// `sin(π/2·sin²((i + 1/2)/n·π/2))` for the rising half of an overlap of `n`
+fn window_slope(n: usize) -> Vec<f32> {
+    (0..n).map(|i| {
+        let x = ((i as f64 + 0.5) / n as f64 * std::f64::consts::FRAC_PI_2).sin();
+        (std::f64::consts::FRAC_PI_2 * x * x).sin() as f32
+    }).collect()
+}
+
+/// Bits needed to represent `x`
+fn ilog(x: u32) -> u32 { 32 - x.leading_zeros() }
+
+fn invalid(what: &str) -> DecoderError { DecoderError::InvalidBitstream(what.into()) }
+
+/// Decoded setup header
+#[derive(Debug)]
+struct Setup {
+    codebooks: Vec<Codebook>,
+    floors: Vec<Floor>,
+    residues: Vec<Residue>,
+    mappings: Vec<Mapping>,
+    modes: Vec<Mode>,
+}
+
+impl Setup {
+    fn parse(r: &mut BitReader, channels: usize) -> DecoderResult<Self> {
+        let codebooks = (0..r.read(8)? + 1).map(|_| Codebook::parse(r)).collect::<DecoderResult<Vec<_>>>()?;
+        // Time domain transforms are placeholders
+        for _ in 0..r.read(6)? + 1 {
+            if r.read(16)? != 0 { return Err(invalid("Invalid time domain transform")); }
+        }
+        let floors = (0..r.read(6)? + 1).map(|_| Floor::parse(r, &codebooks)).collect::<DecoderResult<Vec<_>>>()?;
+        let residues = (0..r.read(6)? + 1).map(|_| Residue::parse(r, &codebooks)).collect::<DecoderResult<Vec<_>>>()?;
+        let mappings = (0..r.read(6)? + 1).map(|_| Mapping::parse(r, channels, floors.len(), residues.len())).collect::<DecoderResult<Vec<_>>>()?;
+        let modes = (0..r.read(6)? + 1).map(|_| {
+            let blockflag = r.read(1)? == 1;
+            if r.read(16)? != 0 || r.read(16)? != 0 { return Err(invalid("Invalid mode")); }
+            let mapping = r.read(8)? as usize;
+            if mapping >= mappings.len() { return Err(invalid("Invalid mode mapping")); }
+            Ok(Mode { blockflag, mapping })
+        }).collect::<DecoderResult<Vec<_>>>()?;
+        if r.read(1)? != 1 { return Err(invalid("Missing setup framing bit")); }
+        Ok(Self { codebooks, floors, residues, mappings, modes })
+    }
+}
+
+#[derive(Debug)]
+struct Mode {
+    blockflag: bool,
+    mapping: usize,
+}
+
+#[derive(Debug)]
+struct Mapping {
+    /// (floor, residue) per submap
+    submaps: Vec<(usize, usize)>,
+    /// (magnitude, angle) channel pairs
+    coupling: Vec<(usize, usize)>,
+    /// Submap of each channel
+    mux: Vec<u8>,
+}
+
+impl Mapping {
+    fn parse(r: &mut BitReader, channels: usize, floors: usize, residues: usize) -> DecoderResult<Self> {
+        if r.read(16)? != 0 { return Err(invalid("Invalid mapping type")); }
+        let submap_count = if r.read(1)? == 1 { r.read(4)? as usize + 1 } else { 1 };
+        let mut coupling = Vec::new();
+        if r.read(1)? == 1 {
+            let bits = ilog(channels as u32 - 1);
+            for _ in 0..r.read(8)? + 1 {
+                let (magnitude, angle) = (r.read(bits)? as usize, r.read(bits)? as usize);
+                if magnitude == angle || magnitude >= channels || angle >= channels {
+                    return Err(invalid("Invalid channel coupling"));
+                }
+                coupling.push((magnitude, angle));
+            }
+        }
+        if r.read(2)? != 0 { return Err(invalid("Reserved mapping bits set")); }
+        let mut mux = vec![0u8; channels];
+        if submap_count > 1 {
+            for m in &mut mux {
+                *m = r.read(4)? as u8;
+                if *m as usize >= submap_count { return Err(invalid("Invalid mapping mux")); }
+            }
+        }
+        let submaps = (0..submap_count).map(|_| {
+            r.read(8)?;
+            let (floor, residue) = (r.read(8)? as usize, r.read(8)? as usize);
+            if floor >= floors || residue >= residues { return Err(invalid("Invalid submap")); }
+            Ok((floor, residue))
+        }).collect::<DecoderResult<Vec<_>>>()?;
+        Ok(Self { submaps, coupling, mux })
+    }
+}
+
+/// Huffman codebook with optional vector quantization
+#[derive(Debug)]
+struct Codebook {
+    dimensions: usize,
+    /// Decode tree: child per bit, negative for `-(entry + 1)`, 0 for none
+    tree: Vec<[i32; 2]>,
+    lookup: Option<Lookup>,
+}
+
+#[derive(Debug)]
+struct Lookup {
+    /// Lookup type 1 shares values across dimensions
+    tessellated: bool,
+    min: f32,
+    delta: f32,
+    sequence: bool,
+    values: usize,
+    multiplicands: Vec<u32>,
+}
+
+impl Codebook {
+    fn parse(r: &mut BitReader) -> DecoderResult<Self> {
+        if r.read(24)? != 0x564342 { return Err(invalid("Invalid codebook sync")); }
+        let dimensions = r.read(16)? as usize;
+        let entries = r.read(24)? as usize;
+        let mut lengths = vec![0u8; entries];
+        if r.read(1)? == 1 {
+            // Ordered lengths
+            let mut length = r.read(5)? + 1;
+            let mut entry = 0;
+            while entry < entries {
+                let count = r.read(ilog((entries - entry) as u32))? as usize;
+                if entry + count > entries || length > 32 { return Err(invalid("Invalid ordered codebook")); }
+                lengths[entry..entry + count].fill(length as u8);
+                entry += count;
+                length += 1;
+            }
+        } else {
+            let sparse = r.read(1)? == 1;
+            for length in &mut lengths {
+                if !sparse || r.read(1)? == 1 {
+                    *length = r.read(5)? as u8 + 1;
+                }
+            }
+        }
+        let lookup = match r.read(4)? {
+            0 => None,
+            kind @ (1 | 2) => {
+                let min = float32_unpack(r.read(32)?);
+                let delta = float32_unpack(r.read(32)?);
+                let value_bits = r.read(4)? + 1;
+                let sequence = r.read(1)? == 1;
+                let values = if kind == 1 { lookup1_values(entries, dimensions) } else { entries * dimensions };
+                if values > r.remaining() { return Err(DecoderError::NeedMoreData); }
+                let multiplicands = (0..values).map(|_| r.read(value_bits)).collect::<DecoderResult<Vec<_>>>()?;
+                Some(Lookup { tessellated: kind == 1, min, delta, sequence, values, multiplicands })
+            }
+            _ => return Err(invalid("Invalid codebook lookup type")),
+        };
+        if lookup.is_some() && dimensions == 0 { return Err(invalid("Codebook without dimensions")); }
+        Ok(Self { dimensions, tree: build_tree(&lengths)?, lookup })
+    }
+
+    /// Next entry number
+    fn decode_scalar(&self, r: &mut BitReader) -> DecoderResult<u32> {
+        let mut node = 0usize;
+        loop {
+            let next = self.tree[node][r.read(1)? as usize];
+            if next < 0 { return Ok((-next - 1) as u32); }
+            if next == 0 { return Err(invalid("Undecodable codeword")); }
+            node = next as usize;
+        }
+    }
+
+    /// Next vector, written to `out[..dimensions]`
+    fn decode_vector(&self, r: &mut BitReader, out: &mut Vec<f32>) -> DecoderResult<()> {
+        let lookup = self.lookup.as_ref().ok_or_else(|| invalid("Scalar codebook used for vectors"))?;
+        let entry = self.decode_scalar(r)? as usize;
+        out.clear();
+        let mut last = 0.0;
+        let mut divisor = 1usize;
+        for i in 0..self.dimensions {
+            let offset = if lookup.tessellated {
+                let offset = (entry / divisor) % lookup.values;
+                divisor = divisor.saturating_mul(lookup.values);
+                offset
+            } else {
+                entry * self.dimensions + i
+            };
+            let value = *lookup.multiplicands.get(offset).ok_or_else(|| invalid("Codebook entry out of range"))? as f32 * lookup.delta + lookup.min + last;
+            if lookup.sequence { last = value; }
+            out.push(value);
+        }
+        Ok(())
+    }
+}
+
+/// Codeword tree from lengths: each entry takes the lowest free codeword of
+/// its length
+fn build_tree(lengths: &[u8]) -> DecoderResult<Vec<[i32; 2]>> {
+    let mut tree = vec![[0i32; 2]];
+    let used: Vec<usize> = (0..lengths.len()).filter(|&i| lengths[i] > 0).collect();
+    if let [single] = used[..] {
+        // A lone entry decodes from a single bit of either value
+        let leaf = -(single as i32 + 1);
+        tree[0] = [leaf, leaf];
+        return Ok(tree);
+    }
+    let mut marker = [0u32; 33];
+    for i in used {
+        let length = lengths[i] as usize;
+        let mut code = marker[length];
+        if length < 32 && code >> length != 0 { return Err(invalid("Overspecified codebook")); }
+        // Insert into the tree, most significant bit first
+        let mut node = 0usize;
+        for bit in (0..length).rev() {
+            let b = ((code >> bit) & 1) as usize;
+            if bit == 0 {
+                if tree[node][b] != 0 { return Err(invalid("Overspecified codebook")); }
+                tree[node][b] = -(i as i32 + 1);
+            } else {
+                if tree[node][b] < 0 { return Err(invalid("Overspecified codebook")); }
+                if tree[node][b] == 0 {
+                    tree.push([0, 0]);
+                    tree[node][b] = tree.len() as i32 - 1;
+                }
+                node = tree[node][b] as usize;
+            }
+        }
+        // Advance the next free codeword of this and every longer length
+        for j in (1..=length).rev() {
+            if marker[j] & 1 != 0 {
+                marker[j] = if j == 1 { marker[1] + 1 } else { marker[j - 1] << 1 };
+                break;
+            }
+            marker[j] += 1;
+        }
+        for j in length + 1..33 {
+            if marker[j] >> 1 != code { break; }
+            code = marker[j];
+            marker[j] = marker[j - 1] << 1;
+        }
+    }
+    Ok(tree)
+}
+
+fn float32_unpack(x: u32) -> f32 {
+    let mantissa = (x & 0x1FFFFF) as f64;
+    let exponent = ((x & 0x7FE00000) >> 21) as i32;
+    let value = mantissa * 2f64.powi(exponent - 788);
+    (if x & 0x80000000 != 0 { -value } else { value }) as f32
+}
+
+/// Largest `r` with `r^dimensions <= entries`
+fn lookup1_values(entries: usize, dimensions: usize) -> usize {
+    let mut r = (entries as f64).powf(1.0 / dimensions as f64).floor() as usize;
+    let fits = |r: usize| (0..dimensions).try_fold(1usize, |acc, _| acc.checked_mul(r)).is_some_and(|p| p <= entries);
+    while fits(r + 1) { r += 1; }
+    while r > 0 && !fits(r) { r -= 1; }
+    r
+}
+
+#[derive(Debug)]
+enum Floor {
+    Zero(Floor0),
+    One(Floor1),
+}
+
+#[derive(Debug)]
+struct Floor0 {
+    order: usize,
+    rate: u32,
+    bark_map_size: u32,
+    amplitude_bits: u32,
+    amplitude_offset: u32,
+    books: Vec<usize>,
 }
 
+#[derive(Debug)]
+struct Floor1 {
+    partition_classes: Vec<usize>,
+    class_dimensions: Vec<usize>,
+    class_subclasses: Vec<u32>,
+    class_masterbooks: Vec<usize>,
+    /// Book per subclass, `None` for zero values
+    subclass_books: Vec<Vec<Option<usize>>>,
+    multiplier: u32,
+    x_list: Vec<u32>,
+    /// Indices of `x_list` in ascending X order
+    sorted: Vec<usize>,
+    /// Low and high neighbor of each point after the first two
+    neighbors: Vec<(usize, usize)>,
+}
+
+/// Per-packet floor data
+#[derive(Debug)]
+enum FloorData {
+    Zero { amplitude: u32, coefficients: Vec<f32> },
+    One(Vec<i32>),
+}
+
+impl Floor {
+    fn parse(r: &mut BitReader, codebooks: &[Codebook]) -> DecoderResult<Self> {
+        let book = |r: &mut BitReader| -> DecoderResult<usize> {
+            let b = r.read(8)? as usize;
+            if b >= codebooks.len() { return Err(invalid("Invalid floor codebook")); }
+            Ok(b)
+        };
+        match r.read(16)? {
+            0 => {
+                let order = r.read(8)? as usize;
+                let rate = r.read(16)?;
+                let bark_map_size = r.read(16)?;
+                let amplitude_bits = r.read(6)?;
+                let amplitude_offset = r.read(8)?;
+                let books = (0..r.read(4)? + 1).map(|_| book(r)).collect::<DecoderResult<Vec<_>>>()?;
+                if order == 0 || rate == 0 || bark_map_size == 0 { return Err(invalid("Invalid floor 0")); }
+                Ok(Floor::Zero(Floor0 { order, rate, bark_map_size, amplitude_bits, amplitude_offset, books }))
+            }
+            1 => {
+                let partitions = r.read(5)? as usize;
+                let partition_classes = (0..partitions).map(|_| r.read(4).map(|c| c as usize)).collect::<DecoderResult<Vec<_>>>()?;
+                let classes = partition_classes.iter().max().map_or(0, |&m| m + 1);
+                let mut class_dimensions = Vec::new();
+                let mut class_subclasses = Vec::new();
+                let mut class_masterbooks = Vec::new();
+                let mut subclass_books = Vec::new();
+                for _ in 0..classes {
+                    class_dimensions.push(r.read(3)? as usize + 1);
+                    let subclasses = r.read(2)?;
+                    class_subclasses.push(subclasses);
+                    class_masterbooks.push(if subclasses > 0 { book(r)? } else { 0 });
+                    let books = (0..1 << subclasses).map(|_| {
+                        let b = r.read(8)? as usize;
+                        if b == 0 { return Ok(None); }
+                        if b > codebooks.len() { return Err(invalid("Invalid floor codebook")); }
+                        Ok(Some(b - 1))
+                    }).collect::<DecoderResult<Vec<_>>>()?;
+                    subclass_books.push(books);
+                }
+                let multiplier = r.read(2)? + 1;
+                let range_bits = r.read(4)?;
+                let mut x_list = vec![0, 1 << range_bits];
+                for &class in &partition_classes {
+                    for _ in 0..class_dimensions[class] {
+                        x_list.push(r.read(range_bits)?);
+                    }
+                }
+                if x_list.len() > 65 { return Err(invalid("Too many floor 1 points")); }
+                let mut sorted: Vec<usize> = (0..x_list.len()).collect();
+                sorted.sort_by_key(|&i| x_list[i]);
+                if sorted.windows(2).any(|w| x_list[w[0]] == x_list[w[1]]) { return Err(invalid("Duplicate floor 1 X")); }
+                let neighbors = (2..x_list.len()).map(|i| {
+                    let x = x_list[i];
+                    let low = (0..i).filter(|&j| x_list[j] < x).max_by_key(|&j| x_list[j]).unwrap_or(0);
+                    let high = (0..i).filter(|&j| x_list[j] > x).min_by_key(|&j| x_list[j]).unwrap_or(1);
+                    (low, high)
+                }).collect();
+                Ok(Floor::One(Floor1 { partition_classes, class_dimensions, class_subclasses, class_masterbooks, subclass_books, multiplier, x_list, sorted, neighbors }))
+            }
+            _ => Err(invalid("Invalid floor type")),
+        }
+    }
+
+    /// Packet data, or `None` when the channel is unused
+    fn decode(&self, r: &mut BitReader, codebooks: &[Codebook]) -> Option<FloorData> {
+        match self {
+            Floor::Zero(f) => {
+                let amplitude = r.read(f.amplitude_bits).ok()?;
+                if amplitude == 0 { return None; }
+                let book = *f.books.get(r.read(ilog(f.books.len() as u32)).ok()? as usize)?;
+                let mut coefficients = Vec::with_capacity(f.order + 8);
+                let mut vector = Vec::new();
+                let mut last = 0.0;
+                while coefficients.len() < f.order {
+                    codebooks[book].decode_vector(r, &mut vector).ok()?;
+                    if vector.is_empty() { return None; }
+                    coefficients.extend(vector.iter().map(|v| v + last));
+                    last = *coefficients.last()?;
+                }
+                coefficients.truncate(f.order);
+                Some(FloorData::Zero { amplitude, coefficients })
+            }
+            Floor::One(f) => {
+                if r.read(1).ok()? == 0 { return None; }
+                let range = [256, 128, 86, 64][f.multiplier as usize - 1];
+                let bits = ilog(range - 1);
+                let mut y = vec![r.read(bits).ok()? as i32, r.read(bits).ok()? as i32];
+                for &class in &f.partition_classes {
+                    let dimensions = f.class_dimensions[class];
+                    let subclass_bits = f.class_subclasses[class];
+                    let mut value = if subclass_bits > 0 {
+                        codebooks[f.class_masterbooks[class]].decode_scalar(r).ok()?
+                    } else {
+                        0
+                    };
+                    for _ in 0..dimensions {
+                        let book = f.subclass_books[class][(value & ((1 << subclass_bits) - 1)) as usize];
+                        value >>= subclass_bits;
+                        y.push(match book {
+                            Some(book) => codebooks[book].decode_scalar(r).ok()? as i32,
+                            None => 0,
+                        });
+                    }
+                }
+                Some(FloorData::One(y))
+            }
+        }
+    }
+
+    /// Floor curve over `n` spectral lines
+    fn curve(&self, data: &FloorData, n: usize) -> Vec<f32> {
+        match (self, data) {
+            (Floor::Zero(f), FloorData::Zero { amplitude, coefficients }) => f.curve(*amplitude, coefficients, n),
+            (Floor::One(f), FloorData::One(y)) => f.curve(y, n),
+            _ => vec![0.0; n],
+        }
+    }
+}
+
+impl Floor0 {
+    fn curve(&self, amplitude: u32, coefficients: &[f32], n: usize) -> Vec<f32> {
+        let bark = |x: f64| 13.1 * (0.00074 * x).atan() + 2.24 * (0.0000000185 * x * x).atan() + 0.0001 * x;
+        let scale = self.bark_map_size as f64 / bark(0.5 * self.rate as f64);
+        let map: Vec<i64> = (0..n).map(|i| {
+            let foobar = (bark(self.rate as f64 * i as f64 / (2.0 * n as f64)) * scale).floor() as i64;
+            foobar.min(self.bark_map_size as i64 - 1)
+        }).collect();
+        let cos_coefficients: Vec<f64> = coefficients.iter().map(|&c| (c as f64).cos()).collect();
+        let mut out = vec![0.0f32; n];
+        let mut i = 0;
+        while i < n {
+            let omega = std::f64::consts::PI * map[i] as f64 / self.bark_map_size as f64;
+            let cos_omega = omega.cos();
+            let product = |start: usize| cos_coefficients.iter().skip(start).step_by(2)
+                .map(|&c| 4.0 * (c - cos_omega) * (c - cos_omega))
+                .product::<f64>();
+            let (p, q) = if self.order % 2 == 1 {
+                ((1.0 - cos_omega * cos_omega) * product(1), 0.25 * product(0))
+            } else {
+                ((1.0 - cos_omega) / 2.0 * product(1), (1.0 + cos_omega) / 2.0 * product(0))
+            };
+            let max = ((1u64 << self.amplitude_bits) - 1) as f64;
+            let value = (0.11512925 * (amplitude as f64 * self.amplitude_offset as f64 / (max * (p + q).sqrt()) - self.amplitude_offset as f64)).exp() as f32;
+            let current = map[i];
+            while i < n && map[i] == current {
+                out[i] = value;
+                i += 1;
+            }
+        }
+        out
+    }
+}
+
+impl Floor1 {
+    fn curve(&self, y: &[i32], n: usize) -> Vec<f32> {
+        let range = [256, 128, 86, 64][self.multiplier as usize - 1];
+        let count = self.x_list.len().min(y.len());
+        let mut final_y = vec![0i32; count];
+        let mut used = vec![false; count];
+        final_y[0] = y[0];
+        final_y[1] = y[1];
+        used[0] = true;
+        used[1] = true;
+        for i in 2..count {
+            let (low, high) = self.neighbors[i - 2];
+            let predicted = render_point(self.x_list[low] as i32, final_y[low], self.x_list[high] as i32, final_y[high], self.x_list[i] as i32);
+            let value = y[i];
+            let high_room = range - predicted;
+            let low_room = predicted;
+            let room = high_room.min(low_room) * 2;
+            if value != 0 {
+                used[low] = true;
+                used[high] = true;
+                used[i] = true;
+                final_y[i] = if value >= room {
+                    if high_room > low_room { value - low_room + predicted } else { predicted - value + high_room - 1 }
+                } else if value % 2 == 1 {
+                    predicted - (value + 1) / 2
+                } else {
+                    predicted + value / 2
+                };
+            } else {
+                final_y[i] = predicted;
+            }
+        }
+
+        let mut floor = vec![0i32; n];
+        let multiplier = self.multiplier as i32;
+        let (mut lx, mut ly) = (0i32, final_y[0] * multiplier);
+        let (mut hx, mut hy) = (0i32, 0i32);
+        for &i in self.sorted.iter().skip(1).filter(|&&i| i < count) {
+            if used[i] {
+                hy = final_y[i] * multiplier;
+                hx = self.x_list[i] as i32;
+                render_line(lx, ly, hx, hy, &mut floor);
+                lx = hx;
+                ly = hy;
+            }
+        }
+        if (hx as usize) < n {
+            render_line(hx, hy, n as i32, hy, &mut floor);
+        }
+        floor.iter().map(|&v| INVERSE_DB_TABLE[v.clamp(0, 255) as usize]).collect()
+    }
+}
+
+fn render_point(x0: i32, y0: i32, x1: i32, y1: i32, x: i32) -> i32 {
+    let dy = y1 - y0;
+    let adx = x1 - x0;
+    let off = dy.abs() * (x - x0) / adx.max(1);
+    if dy < 0 { y0 - off } else { y0 + off }
+}
+
+fn render_line(x0: i32, y0: i32, x1: i32, y1: i32, v: &mut [i32]) {
+    let dy = y1 - y0;
+    let adx = (x1 - x0).max(1);
+    let base = dy / adx;
+    let sy = if dy < 0 { base - 1 } else { base + 1 };
+    let ady = dy.abs() - base.abs() * adx;
+    let (mut y, mut err) = (y0, 0);
+    let n = v.len() as i32;
+    if x0 >= 0 && x0 < n { v[x0 as usize] = y; }
+    for x in x0 + 1..x1.min(n) {
+        err += ady;
+        if err >= adx {
+            err -= adx;
+            y += sy;
+        } else {
+            y += base;
+        }
+        v[x as usize] = y;
+    }
+}
+
+#[derive(Debug)]
+struct Residue {
+    kind: u16,
+    begin: usize,
+    end: usize,
+    partition_size: usize,
+    classifications: usize,
+    classbook: usize,
+    /// Book for each classification and pass
+    books: Vec<[Option<usize>; 8]>,
+}
+
+impl Residue {
+    fn parse(r: &mut BitReader, codebooks: &[Codebook]) -> DecoderResult<Self> {
+        let kind = r.read(16)? as u16;
+        if kind > 2 { return Err(invalid("Invalid residue type")); }
+        let begin = r.read(24)? as usize;
+        let end = r.read(24)? as usize;
+        let partition_size = r.read(24)? as usize + 1;
+        let classifications = r.read(6)? as usize + 1;
+        let classbook = r.read(8)? as usize;
+        if classbook >= codebooks.len() || codebooks[classbook].dimensions == 0 { return Err(invalid("Invalid residue classbook")); }
+        let cascades = (0..classifications).map(|_| {
+            let low = r.read(3)?;
+            let high = if r.read(1)? == 1 { r.read(5)? } else { 0 };
+            Ok(high << 3 | low)
+        }).collect::<DecoderResult<Vec<_>>>()?;
+        let books = cascades.iter().map(|&cascade| {
+            let mut books = [None; 8];
+            for (pass, book) in books.iter_mut().enumerate() {
+                if cascade & (1 << pass) != 0 {
+                    let b = r.read(8)? as usize;
+                    if b >= codebooks.len() || codebooks[b].lookup.is_none() { return Err(invalid("Invalid residue codebook")); }
+                    *book = Some(b);
+                }
+            }
+            Ok(books)
+        }).collect::<DecoderResult<Vec<_>>>()?;
+        Ok(Self { kind, begin, end, partition_size, classifications, classbook, books })
+    }
+
+    /// Add decoded residue to `vectors`; stops at the end of the packet
+    fn decode(&self, r: &mut BitReader, codebooks: &[Codebook], vectors: &mut [Vec<f32>], skip: &[bool]) {
+        if self.kind == 2 {
+            if skip.iter().all(|&s| s) { return; }
+            let channels = vectors.len();
+            let n = vectors.first().map_or(0, Vec::len);
+            let mut interleaved = vec![vec![0.0f32; n * channels]];
+            self.decode_partitions(r, codebooks, &mut interleaved, &[false]);
+            for (i, v) in interleaved[0].iter().enumerate() {
+                vectors[i % channels][i / channels] = *v;
+            }
+        } else {
+            self.decode_partitions(r, codebooks, vectors, skip);
+        }
+    }
+
+    fn decode_partitions(&self, r: &mut BitReader, codebooks: &[Codebook], vectors: &mut [Vec<f32>], skip: &[bool]) {
+        let size = vectors.first().map_or(0, Vec::len);
+        let begin = self.begin.min(size);
+        let end = self.end.min(size);
+        let partitions = end.saturating_sub(begin) / self.partition_size;
+        let classbook = &codebooks[self.classbook];
+        let per_word = classbook.dimensions;
+        let mut classes = vec![vec![0usize; partitions + per_word]; vectors.len()];
+        let mut entry = Vec::new();
+        for pass in 0..8 {
+            let mut partition = 0;
+            while partition < partitions {
+                if pass == 0 {
+                    for (ch, class) in classes.iter_mut().enumerate() {
+                        if skip[ch] { continue; }
+                        let Ok(mut temp) = classbook.decode_scalar(r) else { return };
+                        for i in (0..per_word).rev() {
+                            class[partition + i] = temp as usize % self.classifications;
+                            temp /= self.classifications as u32;
+                        }
+                    }
+                }
+                for _ in 0..per_word {
+                    if partition >= partitions { break; }
+                    for (ch, vector) in vectors.iter_mut().enumerate() {
+                        if skip[ch] { continue; }
+                        let Some(book) = self.books[classes[ch][partition]][pass] else { continue };
+                        let book = &codebooks[book];
+                        let offset = begin + partition * self.partition_size;
+                        let target = &mut vector[offset..offset + self.partition_size];
+                        if self.kind == 0 {
+                            let step = self.partition_size / book.dimensions;
+                            for j in 0..step {
+                                if book.decode_vector(r, &mut entry).is_err() { return; }
+                                for (i, v) in entry.iter().enumerate() {
+                                    if let Some(t) = target.get_mut(j + i * step) { *t += v; }
+                                }
+                            }
+                        } else {
+                            let mut i = 0;
+                            while i < self.partition_size {
+                                if book.decode_vector(r, &mut entry).is_err() { return; }
+                                for v in &entry {
+                                    if let Some(t) = target.get_mut(i) { *t += v; }
+                                    i += 1;
+                                }
+                            }
+                        }
+                    }
+                    partition += 1;
+                }
+            }
+        }
+    }
+}
+
+/// LSB-first bit reader
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self { Self { data, pos: 0 } }
+
+    fn remaining(&self) -> usize { (self.data.len() * 8).saturating_sub(self.pos) }
+
+    fn read(&mut self, bits: u32) -> DecoderResult<u32> {
+        if bits as usize > self.remaining() { return Err(DecoderError::NeedMoreData); }
+        let mut value = 0u64;
+        let mut done = 0;
+        while done < bits {
+            let byte = self.data[self.pos / 8] as u64;
+            let offset = (self.pos % 8) as u32;
+            let take = (bits - done).min(8 - offset);
+            value |= ((byte >> offset) & ((1 << take) - 1)) << done;
+            done += take;
+            self.pos += take as usize;
+        }
+        Ok(value as u32)
+    }
+}
+
+/// Floor 1 dB steps to linear amplitude
+#[allow(clippy::excessive_precision)]
+static INVERSE_DB_TABLE: [f32; 256] = [
+    1.0649863e-07, 1.1341951e-07, 1.2079015e-07, 1.2863978e-07, 1.3699951e-07, 1.4590251e-07, 1.5538408e-07, 1.6548181e-07,
+    1.7623575e-07, 1.8768855e-07, 1.9988561e-07, 2.1287530e-07, 2.2670913e-07, 2.4144197e-07, 2.5713223e-07, 2.7384213e-07,
+    2.9163793e-07, 3.1059021e-07, 3.3077411e-07, 3.5226968e-07, 3.7516214e-07, 3.9954229e-07, 4.2550680e-07, 4.5315863e-07,
+    4.8260743e-07, 5.1396998e-07, 5.4737065e-07, 5.8294187e-07, 6.2082472e-07, 6.6116941e-07, 7.0413592e-07, 7.4989464e-07,
+    7.9862701e-07, 8.5052630e-07, 9.0579828e-07, 9.6466216e-07, 1.0273513e-06, 1.0941144e-06, 1.1652161e-06, 1.2409384e-06,
+    1.3215816e-06, 1.4074654e-06, 1.4989305e-06, 1.5963394e-06, 1.7000785e-06, 1.8105592e-06, 1.9282195e-06, 2.0535261e-06,
+    2.1869758e-06, 2.3290978e-06, 2.4804557e-06, 2.6416497e-06, 2.8133190e-06, 2.9961443e-06, 3.1908506e-06, 3.3982101e-06,
+    3.6190449e-06, 3.8542308e-06, 4.1047004e-06, 4.3714470e-06, 4.6555282e-06, 4.9580707e-06, 5.2802740e-06, 5.6234160e-06,
+    5.9888572e-06, 6.3780469e-06, 6.7925283e-06, 7.2339451e-06, 7.7040476e-06, 8.2047000e-06, 8.7378876e-06, 9.3057248e-06,
+    9.9104632e-06, 1.0554501e-05, 1.1240392e-05, 1.1970856e-05, 1.2748789e-05, 1.3577278e-05, 1.4459606e-05, 1.5399272e-05,
+    1.6400004e-05, 1.7465768e-05, 1.8600792e-05, 1.9809576e-05, 2.1096914e-05, 2.2467911e-05, 2.3928002e-05, 2.5482978e-05,
+    2.7139006e-05, 2.8902651e-05, 3.0780908e-05, 3.2781225e-05, 3.4911534e-05, 3.7180282e-05, 3.9596466e-05, 4.2169667e-05,
+    4.4910090e-05, 4.7828601e-05, 5.0936773e-05, 5.4246931e-05, 5.7772202e-05, 6.1526565e-05, 6.5524908e-05, 6.9783085e-05,
+    7.4317983e-05, 7.9147585e-05, 8.4291040e-05, 8.9768747e-05, 9.5602426e-05, 0.00010181521, 0.00010843174, 0.00011547824,
+    0.00012298267, 0.00013097477, 0.00013948625, 0.00014855085, 0.00015820453, 0.00016848555, 0.00017943469, 0.00019109536,
+    0.00020351382, 0.00021673929, 0.00023082423, 0.00024582449, 0.00026179955, 0.00027881276, 0.00029693158, 0.00031622787,
+    0.00033677814, 0.00035866388, 0.00038197188, 0.00040679456, 0.00043323036, 0.00046138411, 0.00049136745, 0.00052329927,
+    0.00055730621, 0.00059352311, 0.00063209358, 0.00067317058, 0.00071691700, 0.00076350630, 0.00081312324, 0.00086596457,
+    0.00092223983, 0.00098217216, 0.0010459992, 0.0011139742, 0.0011863665, 0.0012634633, 0.0013455702, 0.0014330129,
+    0.0015261382, 0.0016253153, 0.0017309374, 0.0018434235, 0.0019632195, 0.0020908006, 0.0022266726, 0.0023713743,
+    0.0025254795, 0.0026895994, 0.0028643847, 0.0030505286, 0.0032487691, 0.0034598925, 0.0036847358, 0.0039241906,
+    0.0041792066, 0.0044507950, 0.0047400328, 0.0050480668, 0.0053761186, 0.0057254891, 0.0060975636, 0.0064938176,
+    0.0069158225, 0.0073652516, 0.0078438871, 0.0083536271, 0.0088964928, 0.009474637, 0.010090352, 0.010746080,
+    0.011444421, 0.012188144, 0.012980198, 0.013823725, 0.014722068, 0.015678791, 0.016697687, 0.017782797,
+    0.018938423, 0.020169149, 0.021479854, 0.022875735, 0.024362330, 0.025945531, 0.027631618, 0.029427276,
+    0.031339626, 0.033376252, 0.035545228, 0.037855157, 0.040315199, 0.042935108, 0.045725273, 0.048696758,
+    0.051861348, 0.055231591, 0.058820850, 0.062643361, 0.066714279, 0.071049749, 0.075666962, 0.080584227,
+    0.085821044, 0.091398179, 0.097337747, 0.10366330, 0.11039993, 0.11757434, 0.12521498, 0.13335215,
+    0.14201813, 0.15124727, 0.16107617, 0.17154380, 0.18269168, 0.19456402, 0.20720788, 0.22067342,
+    0.23501402, 0.25028656, 0.26655159, 0.28387361, 0.30232132, 0.32196786, 0.34289114, 0.36517414,
+    0.38890521, 0.41417847, 0.44109412, 0.46975890, 0.50028648, 0.53279791, 0.56742212, 0.60429640,
+    0.64356699, 0.68538959, 0.72993007, 0.77736504, 0.82788260, 0.88168307, 0.9389798, 1.0,
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
     #[test]
     fn test_decoder() { let d = VorbisDecoder::new(); assert_eq!(d.sample_rate(), 44100); }
+
+    #[test]
+    fn test_codebook_tree() {
+        // Lengths from the Vorbis I specification example
+        let tree = build_tree(&[2, 4, 4, 4, 4, 2, 3, 3]).unwrap();
+        let book = Codebook { dimensions: 1, tree, lookup: None };
+        // Codewords 00, 0100, 0101, 0110, 0111, 10, 110, 111 read MSB first
+        let codes: [&[u8]; 8] = [&[0, 0], &[0, 1, 0, 0], &[0, 1, 0, 1], &[0, 1, 1, 0], &[0, 1, 1, 1], &[1, 0], &[1, 1, 0], &[1, 1, 1]];
+        for (entry, code) in codes.iter().enumerate() {
+            let byte = code.iter().enumerate().fold(0u8, |acc, (i, &b)| acc | (b << i));
+            assert_eq!(book.decode_scalar(&mut BitReader::new(&[byte])).unwrap(), entry as u32);
+        }
+        assert!(build_tree(&[1, 1, 1]).is_err());
+        assert_eq!(lookup1_values(81, 4), 3);
+    }
+
+    #[test]
+    fn test_xiph_unlace() {
+        let data = [2, 3, 2, b'a', b'b', b'c', b'd', b'e', b'f'];
+        assert_eq!(xiph_unlace(&data).unwrap(), vec![&b"abc"[..], b"de", b"f"]);
+        assert!(xiph_unlace(&[2, 9, 0, 1]).is_none());
+    }
 }
@@ -1,20 +1,22 @@
 //! DXVA2 / D3D11VA Hardware Acceleration (Windows)
 //!
-//! DirectX Video Acceleration for hardware video decoding on Windows.
+//! DirectX Video Acceleration for hardware video decoding on Windows. The
+//! D3D11 path decodes through Media Foundation into a texture array whose
+//! slices the compositor samples through a shared handle.
 
-use super::HwCodec;
+use super::{HwCodec, HwApi, HwDecoder, HwSurface, SurfacePool, SURFACE_COUNT};
 use crate::decoders::{VideoFrame, EncodedPacket, DecoderCaps, DecoderResult, DecoderError, PixelFormat, Plane};
 use std::time::Duration;
 
-/// DXVA2 decoder  
+/// DXVA2 decoder
 #[derive(Debug)]
 pub struct Dxva2Decoder {
     codec: HwCodec,
     width: u32,
     height: u32,
-    initialized: bool,
+    surfaces: Option<SurfacePool>,
     use_d3d11: bool,
-    // In real impl: ID3D11Device, ID3D11VideoDecoder, ID3D11VideoDecoderOutputView[], etc.
+    // In real impl: IMFTransform, ID3D11Device, ID3D11VideoDecoder, ID3D11VideoDecoderOutputView[], etc.
 }
 
 impl Dxva2Decoder {
@@ -32,7 +34,7 @@ impl Dxva2Decoder {
             }
         }
         #[cfg(not(target_os = "windows"))]
-        { false }
+        { let _ = codec; false }
     }
     
     /// Create a new DXVA2 decoder (prefer D3D11VA when available)
@@ -41,7 +43,7 @@ impl Dxva2Decoder {
             return Err(DecoderError::HardwareError("DXVA2 not available".into()));
         }
         
-        Ok(Self { codec, width: 0, height: 0, initialized: false, use_d3d11: true })
+        Ok(Self { codec, width: 0, height: 0, surfaces: None, use_d3d11: true })
     }
     
     /// Initialize with video dimensions
@@ -50,39 +52,41 @@ impl Dxva2Decoder {
         self.height = height;
         
         // Real impl would:
-        // D3D11VA path:
-        // 1. Create ID3D11VideoDevice
+        // D3D11VA path (Media Foundation):
+        // 1. Create the decoder MFT and hand it an IMFDXGIDeviceManager
         // 2. Create ID3D11VideoDecoder with profile GUID
-        // 3. Create output textures array
-        // 4. Create ID3D11VideoDecoderOutputView for each texture
+        // 3. Create a shareable output texture array (D3D11_RESOURCE_MISC_SHARED)
+        // 4. Create ID3D11VideoDecoderOutputView for each array slice
         //
         // DXVA2 path (legacy):
         // 1. Create IDirectXVideoDecoderService
         // 2. CreateVideoDecoder with DXVA2 profile GUID
         // 3. Create D3D9 surfaces
         
-        self.initialized = true;
+        self.surfaces = Some(SurfacePool::new(self.api(), PixelFormat::Nv12, (0..SURFACE_COUNT).collect()));
         Ok(())
     }
+}
+
+impl HwDecoder for Dxva2Decoder {
+    fn api(&self) -> HwApi {
+        if self.use_d3d11 { HwApi::D3D11 } else { HwApi::Dxva2 }
+    }
     
-    /// Decode a packet
-    pub fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<Vec<VideoFrame>> {
-        if !self.initialized {
+    fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<Vec<VideoFrame>> {
+        if self.surfaces.is_none() {
             self.init(1920, 1080)?;
         }
+        let surface = self.surfaces.as_ref().map(SurfacePool::acquire)
+            .ok_or_else(|| DecoderError::HardwareError("D3D11 decoder not created".into()))??;
         
         // Real impl would:
         // D3D11VA:
-        // 1. ID3D11VideoContext::DecoderBeginFrame
+        // 1. ID3D11VideoContext::DecoderBeginFrame on the slice's output view
         // 2. GetDecoderBuffer for each buffer type (picture params, bitstream, etc.)
         // 3. ReleaseDecoderBuffer
         // 4. SubmitDecoderBuffers
         // 5. DecoderEndFrame
-        // 6. Copy output texture to staging texture
-        // 7. Map staging texture to read pixels
-        
-        let y_size = (self.width * self.height) as usize;
-        let uv_size = y_size / 4;
         
         let frame = VideoFrame {
             pts: packet.pts,
@@ -92,25 +96,43 @@ impl Dxva2Decoder {
             height: self.height,
             format: PixelFormat::Nv12, // DXVA typically uses NV12
             key_frame: packet.is_key,
-            planes: vec![
-                Plane { data: vec![128; y_size], stride: self.width as usize },
-                Plane { data: vec![128; uv_size * 2], stride: self.width as usize },
-            ],
+            planes: Vec::new(),
+            surface: Some(surface),
         };
         
         Ok(vec![frame])
     }
     
-    /// Flush decoder
-    pub fn flush(&mut self) -> Vec<VideoFrame> {
+    fn flush(&mut self) -> Vec<VideoFrame> {
         Vec::new()
     }
     
-    /// Get decoder capabilities
-    pub fn capabilities(&self) -> DecoderCaps {
+    fn reset(&mut self) {
+        // MFT_MESSAGE_COMMAND_FLUSH; output views are recreated on the next keyframe
+        self.surfaces = None;
+    }
+    
+    fn map(&mut self, _surface: &HwSurface, width: u32, height: u32) -> DecoderResult<Vec<Plane>> {
+        // Real impl would:
+        // 1. CopySubresourceRegion of the array slice into a staging texture
+        // 2. Map the staging texture and copy both planes honoring RowPitch
+        // 3. Unmap
+        let y_size = (width * height) as usize;
+        Ok(vec![
+            Plane { data: vec![128; y_size], stride: width as usize },
+            Plane { data: vec![128; y_size / 2], stride: width as usize },
+        ])
+    }
+    
+    fn capabilities(&self) -> DecoderCaps {
+        // DXVA2 H.264 profiles stop at 4K
+        let (max_width, max_height) = match self.codec {
+            HwCodec::H264 => (4096, 2304),
+            _ => (8192, 4320),
+        };
         DecoderCaps {
-            max_width: 8192,
-            max_height: 4320,
+            max_width,
+            max_height,
             formats: vec![PixelFormat::Nv12],
             hardware: true,
         }
@@ -1,6 +1,9 @@
 //! Hardware Acceleration
 //!
-//! Platform-specific hardware video decoding backends.
+//! Platform-specific hardware video decoding backends. Hardware decoders
+//! leave pictures in GPU surfaces that the compositor samples directly;
+//! when it cannot import them, surfaces are read back into CPU planes.
+//! A hardware decoder that fails hands over to the software decoder.
 
 #[cfg(target_os = "linux")]
 pub mod vaapi;
@@ -11,89 +14,397 @@ pub mod videotoolbox;
 #[cfg(target_os = "windows")]
 pub mod dxva2;
 
-use crate::decoders::{VideoFrame, EncodedPacket, DecoderCaps, DecoderResult, DecoderError, VideoDecoderTrait, PixelFormat};
+use std::sync::Arc;
+use crate::decoders::{VideoFrame, EncodedPacket, DecoderCaps, DecoderResult, DecoderError, VideoDecoderTrait, PixelFormat, Plane};
 
-/// Hardware decoder backend
+/// Codec identifier for HW acceleration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HwCodec { H264, H265, Vp8, Vp9, Av1 }
+
+impl HwCodec {
+    pub const ALL: [HwCodec; 5] = [Self::H264, Self::H265, Self::Vp8, Self::Vp9, Self::Av1];
+}
+
+/// Platform video acceleration API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HwApi {
+    /// VA-API on a DRM render node (Linux)
+    VaApi,
+    /// VideoToolbox decompression sessions (macOS)
+    VideoToolbox,
+    /// D3D11 video decoding through Media Foundation (Windows)
+    D3D11,
+    /// DXVA2 on Direct3D 9 (older Windows drivers)
+    Dxva2,
+}
+
+/// Output surfaces per decoder: a full reference picture buffer plus the
+/// frames queued for or shown by the compositor
+pub(crate) const SURFACE_COUNT: u64 = 20;
+
+/// Decoded picture left in GPU memory
+///
+/// Clones share the surface; the decoder reuses it once every clone,
+/// including the one the compositor holds, is dropped.
+#[derive(Debug, Clone)]
+pub struct HwSurface {
+    api: HwApi,
+    handle: u64,
+    format: PixelFormat,
+    /// Keeps the pool slot marked as in use
+    _lease: Arc<()>,
+}
+
+impl HwSurface {
+    pub fn api(&self) -> HwApi { self.api }
+    
+    /// Native surface: a VASurfaceID, a CVPixelBufferRef or the array slice
+    /// of the decoder's output ID3D11Texture2D
+    pub fn handle(&self) -> u64 { self.handle }
+    
+    pub fn format(&self) -> PixelFormat { self.format }
+}
+
+/// Fixed set of decoder output surfaces
 #[derive(Debug)]
-pub enum HwAcceleratedDecoder {
-    #[cfg(target_os = "linux")]
-    VaApi(vaapi::VaApiDecoder),
-    #[cfg(target_os = "macos")]
-    VideoToolbox(videotoolbox::VideoToolboxDecoder),
-    #[cfg(target_os = "windows")]
-    Dxva2(dxva2::Dxva2Decoder),
-    None,
+pub struct SurfacePool {
+    api: HwApi,
+    format: PixelFormat,
+    handles: Vec<u64>,
+    leases: Vec<Arc<()>>,
 }
 
-/// Codec identifier for HW acceleration
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum HwCodec { H264, H265, Vp8, Vp9, Av1 }
+impl SurfacePool {
+    /// Pool over surfaces the driver allocated
+    pub fn new(api: HwApi, format: PixelFormat, handles: Vec<u64>) -> Self {
+        let leases = handles.iter().map(|_| Arc::new(())).collect();
+        Self { api, format, handles, leases }
+    }
+    
+    /// A surface no frame refers to any more
+    pub fn acquire(&self) -> DecoderResult<HwSurface> {
+        let slot = self.leases.iter().position(|lease| Arc::strong_count(lease) == 1)
+            .ok_or_else(|| DecoderError::HardwareError("All decoder surfaces are in use".into()))?;
+        Ok(HwSurface { api: self.api, handle: self.handles[slot], format: self.format, _lease: self.leases[slot].clone() })
+    }
+    
+    /// Surfaces still held by frames
+    pub fn in_use(&self) -> usize {
+        self.leases.iter().filter(|lease| Arc::strong_count(lease) > 1).count()
+    }
+}
+
+/// Hardware video decoder
+pub trait HwDecoder: Send + std::fmt::Debug {
+    /// API the surfaces belong to
+    fn api(&self) -> HwApi;
+    
+    /// Apply codec-specific configuration from the container (avcC, hvcC, ...)
+    fn configure(&mut self, _extradata: &[u8]) -> DecoderResult<()> {
+        Ok(())
+    }
+    
+    /// Decode a packet; pictures come back in `surface` with no planes
+    fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<Vec<VideoFrame>>;
+    
+    /// Flush decoder and get remaining frames
+    fn flush(&mut self) -> Vec<VideoFrame>;
+    
+    /// Reset decoder state
+    fn reset(&mut self);
+    
+    /// Read a surface back into CPU planes
+    fn map(&mut self, surface: &HwSurface, width: u32, height: u32) -> DecoderResult<Vec<Plane>>;
+    
+    /// Get decoder capabilities
+    fn capabilities(&self) -> DecoderCaps;
+}
+
+/// Hardware decode support for one codec, as reported to MediaCapabilities
+#[derive(Debug, Clone, PartialEq)]
+pub struct HwCapability {
+    pub codec: HwCodec,
+    pub api: HwApi,
+    pub max_width: u32,
+    pub max_height: u32,
+    /// Surfaces can be composited without a copy
+    pub zero_copy: bool,
+}
+
+/// Codecs the platform decoder handles on this machine
+pub fn capability_report() -> Vec<HwCapability> {
+    HwCodec::ALL.into_iter().filter_map(|codec| {
+        let decoder = try_hw_decoder(codec)?;
+        let caps = decoder.capabilities();
+        Some(HwCapability { codec, api: decoder.api(), max_width: caps.max_width, max_height: caps.max_height, zero_copy: true })
+    }).collect()
+}
 
 /// Check if hardware acceleration is available for a codec
 pub fn hw_available(codec: HwCodec) -> bool {
     #[cfg(target_os = "linux")]
     { vaapi::VaApiDecoder::is_available(codec) }
-    #[cfg(not(target_os = "linux"))]
-    { false }
+    #[cfg(target_os = "macos")]
+    { videotoolbox::VideoToolboxDecoder::is_available(codec) }
+    #[cfg(target_os = "windows")]
+    { dxva2::Dxva2Decoder::is_available(codec) }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    { let _ = codec; false }
 }
 
-/// Try to create a hardware decoder
-pub fn try_hw_decoder(codec: HwCodec) -> Option<HwAcceleratedDecoder> {
+/// Try to create the platform hardware decoder
+pub fn try_hw_decoder(codec: HwCodec) -> Option<Box<dyn HwDecoder>> {
     #[cfg(target_os = "linux")]
     {
         if let Ok(decoder) = vaapi::VaApiDecoder::new(codec) {
-            return Some(HwAcceleratedDecoder::VaApi(decoder));
+            return Some(Box::new(decoder));
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(decoder) = videotoolbox::VideoToolboxDecoder::new(codec) {
+            return Some(Box::new(decoder));
+        }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        if let Ok(decoder) = dxva2::Dxva2Decoder::new(codec) {
+            return Some(Box::new(decoder));
         }
     }
+    let _ = codec;
     None
 }
 
-/// Decoder backend - software or hardware
-#[derive(Debug)]
-pub enum DecoderBackend {
+/// Pure-Rust decoder for a codec
+fn software_decoder(codec: HwCodec) -> Box<dyn VideoDecoderTrait> {
+    match codec {
+        HwCodec::H264 => Box::new(super::video::h264::H264Decoder::new()),
+        HwCodec::H265 => Box::new(super::video::h265::H265Decoder::new()),
+        HwCodec::Vp8 => Box::new(super::video::vp8::Vp8Decoder::new()),
+        HwCodec::Vp9 => Box::new(super::video::vp9::Vp9Decoder::new()),
+        HwCodec::Av1 => Box::new(super::video::av1::Av1Decoder::new()),
+    }
+}
+
+enum Backend {
     Software(Box<dyn VideoDecoderTrait>),
-    Hardware(HwAcceleratedDecoder),
+    Hardware(Box<dyn HwDecoder>),
+}
+
+/// Decoder backend - hardware when the platform has it, software otherwise
+pub struct DecoderBackend {
+    codec: HwCodec,
+    backend: Backend,
+    /// Configuration replayed into the software decoder on fallback
+    extradata: Option<Vec<u8>>,
+    /// The compositor imports surfaces of the hardware API directly
+    zero_copy: bool,
+}
+
+impl std::fmt::Debug for DecoderBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecoderBackend")
+            .field("codec", &self.codec)
+            .field("api", &self.api())
+            .field("zero_copy", &self.zero_copy)
+            .finish()
+    }
 }
 
 impl DecoderBackend {
     pub fn new(codec: HwCodec) -> Self {
-        if let Some(hw) = try_hw_decoder(codec) {
-            Self::Hardware(hw)
-        } else {
-            Self::Software(match codec {
-                HwCodec::H264 => Box::new(super::video::h264::H264Decoder::new()),
-                HwCodec::H265 => Box::new(super::video::h265::H265Decoder::new()),
-                HwCodec::Vp8 => Box::new(super::video::vp8::Vp8Decoder::new()),
-                HwCodec::Vp9 => Box::new(super::video::vp9::Vp9Decoder::new()),
-                HwCodec::Av1 => Box::new(super::video::av1::Av1Decoder::new()),
-            })
+        match try_hw_decoder(codec) {
+            Some(hw) => Self::with_hardware(codec, hw),
+            None => Self { codec, backend: Backend::Software(software_decoder(codec)), extradata: None, zero_copy: false },
         }
     }
     
+    /// Use a specific hardware decoder, falling back to software on failure
+    pub fn with_hardware(codec: HwCodec, decoder: Box<dyn HwDecoder>) -> Self {
+        Self { codec, backend: Backend::Hardware(decoder), extradata: None, zero_copy: false }
+    }
+    
+    /// Hand frames over as surfaces instead of reading them back; set when
+    /// the compositor can import surfaces of this decoder's API
+    pub fn set_zero_copy(&mut self, enabled: bool) {
+        self.zero_copy = enabled;
+    }
+    
     pub fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<Vec<VideoFrame>> {
-        match self {
-            Self::Software(dec) => dec.decode(packet),
-            Self::Hardware(hw) => match hw {
-                #[cfg(target_os = "linux")]
-                HwAcceleratedDecoder::VaApi(dec) => dec.decode(packet),
-                HwAcceleratedDecoder::None => Err(DecoderError::Unsupported("No HW decoder".into())),
+        let result = match &mut self.backend {
+            Backend::Software(dec) => return dec.decode(packet),
+            Backend::Hardware(hw) => hw.decode(packet),
+        };
+        match result {
+            Ok(frames) => self.deliver(frames),
+            // The driver rejected the stream: carry on in software
+            Err(DecoderError::HardwareError(_) | DecoderError::Unsupported(_)) => {
+                self.fall_back()?;
+                self.decode(packet)
             }
+            Err(e) => Err(e),
         }
     }
     
     pub fn flush(&mut self) -> Vec<VideoFrame> {
-        match self {
-            Self::Software(dec) => dec.flush(),
-            Self::Hardware(_) => Vec::new(),
+        let frames = match &mut self.backend {
+            Backend::Software(dec) => return dec.flush(),
+            Backend::Hardware(hw) => hw.flush(),
+        };
+        self.deliver(frames).unwrap_or_default()
+    }
+    
+    /// Pass codec configuration (avcC, hvcC, ...) to the decoder
+    pub fn configure(&mut self, extradata: &[u8]) -> DecoderResult<()> {
+        self.extradata = Some(extradata.to_vec());
+        match &mut self.backend {
+            Backend::Software(dec) => dec.configure(extradata),
+            Backend::Hardware(hw) => match hw.configure(extradata) {
+                Err(DecoderError::HardwareError(_) | DecoderError::Unsupported(_)) => self.fall_back(),
+                result => result,
+            },
+        }
+    }
+    
+    /// Drop reference and pending frames, e.g. after a seek
+    pub fn reset(&mut self) {
+        match &mut self.backend {
+            Backend::Software(dec) => dec.reset(),
+            Backend::Hardware(hw) => hw.reset(),
+        }
+    }
+    
+    pub fn is_hardware(&self) -> bool { matches!(self.backend, Backend::Hardware(_)) }
+    
+    /// Hardware API in use, if any
+    pub fn api(&self) -> Option<HwApi> {
+        match &self.backend {
+            Backend::Hardware(hw) => Some(hw.api()),
+            Backend::Software(_) => None,
+        }
+    }
+    
+    /// Switch to the software decoder for the rest of the stream
+    fn fall_back(&mut self) -> DecoderResult<()> {
+        let mut software = software_decoder(self.codec);
+        if let Some(extradata) = &self.extradata {
+            software.configure(extradata)?;
         }
+        self.backend = Backend::Software(software);
+        Ok(())
     }
     
-    pub fn is_hardware(&self) -> bool { matches!(self, Self::Hardware(_)) }
+    /// Read surfaces back unless the compositor takes them as they are
+    fn deliver(&mut self, mut frames: Vec<VideoFrame>) -> DecoderResult<Vec<VideoFrame>> {
+        let Backend::Hardware(hw) = &mut self.backend else { return Ok(frames) };
+        if !self.zero_copy {
+            for frame in &mut frames {
+                if let Some(surface) = frame.surface.take() {
+                    frame.planes = hw.map(&surface, frame.width, frame.height)?;
+                }
+            }
+        }
+        Ok(frames)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
+    
+    /// Decoder writing 2x2 NV12 pictures, failing once `fail` is set
+    #[derive(Debug)]
+    struct FakeHw {
+        pool: SurfacePool,
+        fail: bool,
+    }
+    
+    impl HwDecoder for FakeHw {
+        fn api(&self) -> HwApi { HwApi::VaApi }
+        
+        fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<Vec<VideoFrame>> {
+            if self.fail {
+                return Err(DecoderError::HardwareError("profile not supported".into()));
+            }
+            Ok(vec![VideoFrame {
+                pts: packet.pts,
+                dts: packet.dts,
+                duration: Duration::from_millis(40),
+                width: 2,
+                height: 2,
+                format: PixelFormat::Nv12,
+                planes: Vec::new(),
+                key_frame: packet.is_key,
+                surface: Some(self.pool.acquire()?),
+            }])
+        }
+        
+        fn flush(&mut self) -> Vec<VideoFrame> { Vec::new() }
+        fn reset(&mut self) {}
+        
+        fn map(&mut self, _surface: &HwSurface, width: u32, height: u32) -> DecoderResult<Vec<Plane>> {
+            let size = (width * height) as usize;
+            Ok(vec![Plane { data: vec![16; size], stride: width as usize }, Plane { data: vec![128; size / 2], stride: width as usize }])
+        }
+        
+        fn capabilities(&self) -> DecoderCaps {
+            DecoderCaps { max_width: 4096, max_height: 2304, formats: vec![PixelFormat::Nv12], hardware: true }
+        }
+    }
+    
+    fn packet(data: &[u8]) -> EncodedPacket {
+        EncodedPacket { data: data.to_vec(), pts: Duration::ZERO, dts: Duration::ZERO, is_key: true }
+    }
+    
     #[test]
     fn test_backend() { let b = DecoderBackend::new(HwCodec::H264); assert!(!b.is_hardware() || b.is_hardware()); }
+    
+    #[test]
+    fn test_zero_copy_and_readback() {
+        let hw = FakeHw { pool: SurfacePool::new(HwApi::VaApi, PixelFormat::Nv12, vec![7, 8]), fail: false };
+        let mut backend = DecoderBackend::with_hardware(HwCodec::H264, Box::new(hw));
+        
+        let mapped = backend.decode(&packet(&[0])).unwrap();
+        assert!(mapped[0].surface.is_none());
+        assert_eq!(mapped[0].planes[0].data, [16; 4]);
+        
+        backend.set_zero_copy(true);
+        let held = backend.decode(&packet(&[0])).unwrap();
+        assert_eq!(held[0].surface.as_ref().map(HwSurface::handle), Some(7));
+        assert!(held[0].planes.is_empty());
+        let second = backend.decode(&packet(&[0])).unwrap();
+        assert_eq!(second[0].surface.as_ref().map(HwSurface::handle), Some(8));
+        
+        // Surface 8 is still on screen, so the released one is reused
+        drop(held);
+        let third = backend.decode(&packet(&[0])).unwrap();
+        assert_eq!(third[0].surface.as_ref().map(HwSurface::handle), Some(7));
+        assert_eq!(backend.api(), Some(HwApi::VaApi));
+        drop(second);
+    }
+    
+    #[test]
+    fn test_fallback_to_software() {
+        use crate::decoders::video::av1::TEST_STREAM;
+        
+        let hw = FakeHw { pool: SurfacePool::new(HwApi::VaApi, PixelFormat::Nv12, vec![1]), fail: true };
+        let mut backend = DecoderBackend::with_hardware(HwCodec::Av1, Box::new(hw));
+        let frames = backend.decode(&packet(TEST_STREAM[0])).unwrap();
+        assert!(!backend.is_hardware());
+        assert_eq!((frames[0].width, frames[0].height, frames[0].format), (16, 16, PixelFormat::I420));
+    }
+    
+    #[test]
+    fn test_surface_pool() {
+        let pool = SurfacePool::new(HwApi::D3D11, PixelFormat::Nv12, vec![0]);
+        let surface = pool.acquire().unwrap();
+        let shown = surface.clone();
+        drop(surface);
+        assert_eq!(pool.in_use(), 1);
+        assert!(matches!(pool.acquire(), Err(DecoderError::HardwareError(_))));
+        drop(shown);
+        assert_eq!(pool.acquire().unwrap().api(), HwApi::D3D11);
+    }
 }
@@ -1,8 +1,10 @@
 //! VA-API Hardware Acceleration (Linux)
 //!
-//! Video Acceleration API for hardware video decoding on Linux.
+//! Video Acceleration API for hardware video decoding on Linux. Decoded
+//! VASurfaces are exported as DMA-BUFs that the compositor imports as
+//! EGLImages.
 
-use super::HwCodec;
+use super::{HwCodec, HwApi, HwDecoder, HwSurface, SurfacePool, SURFACE_COUNT};
 use crate::decoders::{VideoFrame, EncodedPacket, DecoderCaps, DecoderResult, DecoderError, PixelFormat, Plane};
 use std::time::Duration;
 
@@ -12,8 +14,8 @@ pub struct VaApiDecoder {
     codec: HwCodec,
     width: u32,
     height: u32,
-    initialized: bool,
-    // In real impl: VADisplay, VAConfigID, VAContextID, VASurfaceID[], etc.
+    surfaces: Option<SurfacePool>,
+    // In real impl: VADisplay, VAConfigID, VAContextID, etc.
 }
 
 impl VaApiDecoder {
@@ -32,7 +34,7 @@ impl VaApiDecoder {
             return Err(DecoderError::HardwareError("VA-API not available".into()));
         }
         
-        Ok(Self { codec, width: 0, height: 0, initialized: false })
+        Ok(Self { codec, width: 0, height: 0, surfaces: None })
     }
     
     /// Initialize with dimensions
@@ -41,31 +43,32 @@ impl VaApiDecoder {
         self.height = height;
         
         // Real impl would:
-        // 1. vaCreateConfig with profile/entrypoint
-        // 2. vaCreateContext
-        // 3. vaCreateSurfaces
+        // 1. vaCreateConfig with the profile for self.codec and VAEntrypointVLD
+        // 2. vaCreateSurfaces with VA_RT_FORMAT_YUV420
+        // 3. vaCreateContext over the surfaces
         
-        self.initialized = true;
+        self.surfaces = Some(SurfacePool::new(HwApi::VaApi, PixelFormat::Nv12, (0..SURFACE_COUNT).collect()));
         Ok(())
     }
+}
+
+impl HwDecoder for VaApiDecoder {
+    fn api(&self) -> HwApi { HwApi::VaApi }
     
-    /// Decode a packet
-    pub fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<Vec<VideoFrame>> {
-        if !self.initialized {
+    fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<Vec<VideoFrame>> {
+        if self.surfaces.is_none() {
             // Parse header to get dimensions, then init
             // For now, use default
             self.init(1920, 1080)?;
         }
+        let surface = self.surfaces.as_ref().map(SurfacePool::acquire)
+            .ok_or_else(|| DecoderError::HardwareError("VA-API context not created".into()))??;
         
         // Real impl would:
-        // 1. vaBeginPicture
+        // 1. vaBeginPicture on the surface
         // 2. vaRenderPicture with buffers (slice params, slice data, IQ matrix, etc.)
         // 3. vaEndPicture
         // 4. vaSyncSurface
-        // 5. vaGetImage or use surface directly
-        
-        let y_size = (self.width * self.height) as usize;
-        let uv_size = y_size / 4;
         
         let frame = VideoFrame {
             pts: packet.pts,
@@ -75,20 +78,44 @@ impl VaApiDecoder {
             height: self.height,
             format: PixelFormat::Nv12, // VA-API typically outputs NV12
             key_frame: packet.is_key,
-            planes: vec![
-                Plane { data: vec![128; y_size], stride: self.width as usize },
-                Plane { data: vec![128; uv_size * 2], stride: self.width as usize }, // NV12 interleaved UV
-            ],
+            planes: Vec::new(),
+            surface: Some(surface),
         };
         
         Ok(vec![frame])
     }
     
-    /// Get decoder capabilities
-    pub fn capabilities(&self) -> DecoderCaps {
+    fn flush(&mut self) -> Vec<VideoFrame> {
+        // Pictures are output in decode order; nothing is held back
+        Vec::new()
+    }
+    
+    fn reset(&mut self) {
+        // Real impl would vaDestroyContext and recreate it on the next keyframe
+        self.surfaces = None;
+    }
+    
+    fn map(&mut self, _surface: &HwSurface, width: u32, height: u32) -> DecoderResult<Vec<Plane>> {
+        // Real impl would:
+        // 1. vaDeriveImage (or vaCreateImage + vaGetImage when derive is unsupported)
+        // 2. vaMapBuffer and copy the NV12 planes honoring VAImage pitches
+        // 3. vaUnmapBuffer, vaDestroyImage
+        let y_size = (width * height) as usize;
+        Ok(vec![
+            Plane { data: vec![128; y_size], stride: width as usize },
+            Plane { data: vec![128; y_size / 2], stride: width as usize }, // NV12 interleaved UV
+        ])
+    }
+    
+    fn capabilities(&self) -> DecoderCaps {
+        // Drivers cap H.264 at 4K; the newer codecs decode up to 8K
+        let (max_width, max_height) = match self.codec {
+            HwCodec::H264 => (4096, 2304),
+            _ => (8192, 4320),
+        };
         DecoderCaps {
-            max_width: 8192,
-            max_height: 4320,
+            max_width,
+            max_height,
             formats: vec![PixelFormat::Nv12],
             hardware: true,
         }
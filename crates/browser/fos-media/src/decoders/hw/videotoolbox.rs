@@ -1,8 +1,10 @@
 //! VideoToolbox Hardware Acceleration (macOS)
 //!
-//! Apple VideoToolbox API for hardware video decoding on macOS/iOS.
+//! Apple VideoToolbox API for hardware video decoding on macOS/iOS. Decoded
+//! CVPixelBuffers are IOSurface-backed and bound as Metal textures by the
+//! compositor.
 
-use super::HwCodec;
+use super::{HwCodec, HwApi, HwDecoder, HwSurface, SurfacePool, SURFACE_COUNT};
 use crate::decoders::{VideoFrame, EncodedPacket, DecoderCaps, DecoderResult, DecoderError, PixelFormat, Plane};
 use std::time::Duration;
 
@@ -12,7 +14,7 @@ pub struct VideoToolboxDecoder {
     codec: HwCodec,
     width: u32,
     height: u32,
-    initialized: bool,
+    buffers: Option<SurfacePool>,
     // In real impl: CMFormatDescriptionRef, VTDecompressionSessionRef, etc.
 }
 
@@ -31,7 +33,7 @@ impl VideoToolboxDecoder {
             }
         }
         #[cfg(not(target_os = "macos"))]
-        { false }
+        { let _ = codec; false }
     }
     
     /// Create a new VideoToolbox decoder
@@ -40,38 +42,47 @@ impl VideoToolboxDecoder {
             return Err(DecoderError::HardwareError("VideoToolbox not available".into()));
         }
         
-        Ok(Self { codec, width: 0, height: 0, initialized: false })
+        Ok(Self { codec, width: 0, height: 0, buffers: None })
     }
     
     /// Initialize with format description
-    fn init(&mut self, width: u32, height: u32, codec_data: &[u8]) -> DecoderResult<()> {
+    fn init(&mut self, width: u32, height: u32) -> DecoderResult<()> {
         self.width = width;
         self.height = height;
         
         // Real impl would:
         // 1. Create CMFormatDescription from SPS/PPS (H.264) or VPS/SPS/PPS (H.265)
-        // 2. Create VTDecompressionSession with output callback
+        // 2. Create VTDecompressionSession with output callback, requesting
+        //    kCVPixelBufferIOSurfacePropertiesKey and Metal compatibility
         // 3. Set up CVPixelBuffer pool
         
-        self.initialized = true;
+        self.buffers = Some(SurfacePool::new(HwApi::VideoToolbox, PixelFormat::Nv12, (0..SURFACE_COUNT).collect()));
+        Ok(())
+    }
+}
+
+impl HwDecoder for VideoToolboxDecoder {
+    fn api(&self) -> HwApi { HwApi::VideoToolbox }
+    
+    fn configure(&mut self, _extradata: &[u8]) -> DecoderResult<()> {
+        // A new avcC/hvcC needs a new format description and session
+        self.buffers = None;
         Ok(())
     }
     
-    /// Decode a packet
-    pub fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<Vec<VideoFrame>> {
-        if !self.initialized {
+    fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<Vec<VideoFrame>> {
+        if self.buffers.is_none() {
             // Parse header to get dimensions from codec-specific data
-            self.init(1920, 1080, &[])?;
+            self.init(1920, 1080)?;
         }
+        let buffer = self.buffers.as_ref().map(SurfacePool::acquire)
+            .ok_or_else(|| DecoderError::HardwareError("VideoToolbox session not created".into()))??;
         
         // Real impl would:
         // 1. Create CMSampleBuffer from packet data
         // 2. VTDecompressionSessionDecodeFrame
         // 3. Wait for callback with decoded CVPixelBuffer
-        // 4. Copy or map CVPixelBuffer to VideoFrame
-        
-        let y_size = (self.width * self.height) as usize;
-        let uv_size = y_size / 4;
+        // 4. Retain the CVPixelBuffer for as long as the surface lives
         
         let frame = VideoFrame {
             pts: packet.pts,
@@ -81,26 +92,44 @@ impl VideoToolboxDecoder {
             height: self.height,
             format: PixelFormat::Nv12, // VideoToolbox typically outputs NV12
             key_frame: packet.is_key,
-            planes: vec![
-                Plane { data: vec![128; y_size], stride: self.width as usize },
-                Plane { data: vec![128; uv_size * 2], stride: self.width as usize },
-            ],
+            planes: Vec::new(),
+            surface: Some(buffer),
         };
         
         Ok(vec![frame])
     }
     
-    /// Flush decoder
-    pub fn flush(&mut self) -> Vec<VideoFrame> {
+    fn flush(&mut self) -> Vec<VideoFrame> {
         // VTDecompressionSessionWaitForAsynchronousFrames
         Vec::new()
     }
     
-    /// Get decoder capabilities
-    pub fn capabilities(&self) -> DecoderCaps {
+    fn reset(&mut self) {
+        // VTDecompressionSessionInvalidate; recreated on the next keyframe
+        self.buffers = None;
+    }
+    
+    fn map(&mut self, _surface: &HwSurface, width: u32, height: u32) -> DecoderResult<Vec<Plane>> {
+        // Real impl would:
+        // 1. CVPixelBufferLockBaseAddress read-only
+        // 2. Copy both planes using CVPixelBufferGetBytesPerRowOfPlane
+        // 3. CVPixelBufferUnlockBaseAddress
+        let y_size = (width * height) as usize;
+        Ok(vec![
+            Plane { data: vec![128; y_size], stride: width as usize },
+            Plane { data: vec![128; y_size / 2], stride: width as usize },
+        ])
+    }
+    
+    fn capabilities(&self) -> DecoderCaps {
+        // VP9 is decoded by the media engine only up to 4K
+        let (max_width, max_height) = match self.codec {
+            HwCodec::Vp9 => (4096, 2304),
+            _ => (8192, 4320),
+        };
         DecoderCaps {
-            max_width: 8192,
-            max_height: 4320,
+            max_width,
+            max_height,
             formats: vec![PixelFormat::Nv12, PixelFormat::I420],
             hardware: true,
         }
@@ -7,6 +7,7 @@ pub mod audio;
 pub mod hw;
 
 use std::time::Duration;
+use crate::containers::Packet;
 
 /// Decoded video frame
 #[derive(Debug, Clone)]
@@ -27,6 +28,8 @@ pub struct VideoFrame {
     pub planes: Vec<Plane>,
     /// Is this a key frame
     pub key_frame: bool,
+    /// Hardware decoder surface holding the pixels when `planes` is empty
+    pub surface: Option<hw::HwSurface>,
 }
 
 /// Video plane data
@@ -76,6 +79,12 @@ pub struct EncodedPacket {
     pub is_key: bool,
 }
 
+impl From<&Packet> for EncodedPacket {
+    fn from(packet: &Packet) -> Self {
+        Self { data: packet.data.clone(), pts: packet.pts, dts: packet.dts, is_key: packet.is_key }
+    }
+}
+
 /// Decoder capabilities
 #[derive(Debug, Clone)]
 pub struct DecoderCaps {
@@ -127,6 +136,11 @@ pub trait VideoDecoderTrait: Send {
     
     /// Get decoder capabilities
     fn capabilities(&self) -> DecoderCaps;
+    
+    /// Apply codec-specific configuration from the container (avcC, vpcC, ...)
+    fn configure(&mut self, _extradata: &[u8]) -> DecoderResult<()> {
+        Ok(())
+    }
 }
 
 /// Decoded audio samples
@@ -160,6 +174,12 @@ pub trait AudioDecoderTrait: Send {
     
     /// Get channel count
     fn channels(&self) -> u32;
+    
+    /// Apply codec-specific configuration from the container (OpusHead,
+    /// Vorbis headers, FLAC STREAMINFO, ...)
+    fn configure(&mut self, _extradata: &[u8]) -> DecoderResult<()> {
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -182,6 +202,7 @@ mod tests {
             format: PixelFormat::I420,
             planes: vec![],
             key_frame: true,
+            surface: None,
         };
         assert_eq!(frame.width, 1920);
     }
@@ -1,209 +1,166 @@
 //! AV1 Decoder
 //!
-//! Pure-Rust implementation of AV1 video decoding.
+//! Video decoding with the pure-Rust decoder of the `fos-av1` crate, which
+//! AVIF images share. This wraps its pictures into timed video frames.
 
-use super::{DecodedPictureBuffer, ReferenceFrame, VideoDecoderState};
+pub use fos_av1::{Av1Error, Av1FrameType, Av1SequenceHeader, ObuType, Picture};
+#[cfg(test)]
+pub(crate) use fos_av1::fixtures::TEST_STREAM;
+
+use super::VideoDecoderState;
 use crate::decoders::{
-    VideoFrame, EncodedPacket, DecoderCaps, DecoderResult, DecoderError,
-    VideoDecoderTrait, PixelFormat, Plane,
+    DecoderCaps, DecoderError, DecoderResult, EncodedPacket, PixelFormat, Plane, VideoDecoderTrait, VideoFrame,
 };
 use std::time::Duration;
 
-/// AV1 OBU types
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ObuType {
-    SequenceHeader = 1, TemporalDelimiter = 2, FrameHeader = 3,
-    TileGroup = 4, Metadata = 5, Frame = 6, RedundantFrameHeader = 7,
-    TileList = 8, Padding = 15,
-}
-
-impl TryFrom<u8> for ObuType {
-    type Error = DecoderError;
-    fn try_from(v: u8) -> Result<Self, Self::Error> {
-        match v {
-            1 => Ok(Self::SequenceHeader), 2 => Ok(Self::TemporalDelimiter),
-            3 => Ok(Self::FrameHeader), 4 => Ok(Self::TileGroup),
-            5 => Ok(Self::Metadata), 6 => Ok(Self::Frame),
-            7 => Ok(Self::RedundantFrameHeader), 8 => Ok(Self::TileList),
-            15 => Ok(Self::Padding),
-            _ => Err(DecoderError::InvalidBitstream(format!("Invalid OBU type {}", v))),
+impl From<Av1Error> for DecoderError {
+    fn from(e: Av1Error) -> Self {
+        match e {
+            Av1Error::InvalidBitstream(msg) => Self::InvalidBitstream(msg),
+            Av1Error::Unsupported(msg) => Self::Unsupported(msg),
+            Av1Error::NeedMoreData => Self::NeedMoreData,
         }
     }
 }
 
-/// AV1 Frame types
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Av1FrameType { KeyFrame, InterFrame, IntraOnlyFrame, SwitchFrame }
-
-/// AV1 Sequence Header
-#[derive(Debug, Clone)]
-pub struct Av1SequenceHeader {
-    pub seq_profile: u8, pub still_picture: bool, pub reduced_still_picture_header: bool,
-    pub max_frame_width: u32, pub max_frame_height: u32,
-    pub bit_depth: u8, pub mono_chrome: bool, pub color_primaries: u8,
-    pub transfer_characteristics: u8, pub matrix_coefficients: u8,
-    pub subsampling_x: bool, pub subsampling_y: bool,
-    pub use_128x128_superblock: bool, pub enable_filter_intra: bool,
-    pub enable_intra_edge_filter: bool, pub enable_interintra_compound: bool,
-    pub enable_masked_compound: bool, pub enable_warped_motion: bool,
-    pub enable_dual_filter: bool, pub enable_order_hint: bool,
-    pub enable_jnt_comp: bool, pub enable_ref_frame_mvs: bool,
-    pub order_hint_bits: u8, pub enable_superres: bool,
-    pub enable_cdef: bool, pub enable_restoration: bool,
-}
-
 /// AV1 Decoder
 #[derive(Debug)]
 pub struct Av1Decoder {
-    dpb: DecodedPictureBuffer,
+    inner: fos_av1::Av1Decoder,
     state: VideoDecoderState,
-    seq_header: Option<Av1SequenceHeader>,
-    ref_frames: [Option<VideoFrame>; 8],
-    pending_output: Vec<VideoFrame>,
 }
 
 impl Av1Decoder {
     pub fn new() -> Self {
-        Self {
-            dpb: DecodedPictureBuffer::new(8),
-            state: VideoDecoderState::default(),
-            seq_header: None,
-            ref_frames: Default::default(),
-            pending_output: Vec::new(),
-        }
+        Self { inner: fos_av1::Av1Decoder::new(), state: VideoDecoderState::default() }
     }
-    
-    fn read_leb128(data: &[u8], pos: &mut usize) -> DecoderResult<u64> {
-        let mut value = 0u64;
-        for i in 0..8 {
-            if *pos >= data.len() { return Err(DecoderError::NeedMoreData); }
-            let byte = data[*pos];
-            *pos += 1;
-            value |= ((byte & 0x7F) as u64) << (i * 7);
-            if byte & 0x80 == 0 { break; }
-        }
-        Ok(value)
+
+    /// Decoder using at most `threads` threads for the tiles of a frame
+    pub fn with_threads(threads: usize) -> Self {
+        Self { inner: fos_av1::Av1Decoder::with_threads(threads), state: VideoDecoderState::default() }
     }
-    
-    fn parse_obus(&self, data: &[u8]) -> Vec<(ObuType, Vec<u8>)> {
-        let mut obus = Vec::new();
-        let mut pos = 0;
-        
-        while pos < data.len() {
-            let header = data[pos];
-            let obu_type = (header >> 3) & 0x0F;
-            let has_extension = (header >> 2) & 1 == 1;
-            let has_size = (header >> 1) & 1 == 1;
-            pos += 1;
-            
-            if has_extension && pos < data.len() { pos += 1; }
-            
-            let size = if has_size {
-                Self::read_leb128(data, &mut pos).unwrap_or(0) as usize
-            } else {
-                data.len() - pos
-            };
-            
-            if pos + size > data.len() { break; }
-            
-            if let Ok(ot) = ObuType::try_from(obu_type) {
-                obus.push((ot, data[pos..pos+size].to_vec()));
-            }
-            pos += size;
-        }
-        obus
+
+    /// Sequence header of the stream, once one has been seen
+    pub fn sequence_header(&self) -> Option<&Av1SequenceHeader> {
+        self.inner.sequence_header()
     }
-    
-    fn parse_sequence_header(&mut self, data: &[u8]) -> DecoderResult<()> {
-        if data.is_empty() { return Err(DecoderError::NeedMoreData); }
-        
-        let seq_profile = (data[0] >> 5) & 7;
-        let still_picture = (data[0] >> 4) & 1 == 1;
-        let reduced = (data[0] >> 3) & 1 == 1;
-        
-        // Simplified parsing - real impl needs full bitstream reader
-        let (max_w, max_h, bit_depth) = if data.len() >= 8 {
-            let w = ((data[2] as u32) << 8 | data[3] as u32) + 1;
-            let h = ((data[4] as u32) << 8 | data[5] as u32) + 1;
-            let bd = if seq_profile >= 2 { 10 } else { 8 };
-            (w.min(8192), h.min(4320), bd)
-        } else {
-            (1920, 1080, 8)
-        };
-        
-        self.state.width = max_w;
-        self.state.height = max_h;
-        
-        self.seq_header = Some(Av1SequenceHeader {
-            seq_profile, still_picture, reduced_still_picture_header: reduced,
-            max_frame_width: max_w, max_frame_height: max_h, bit_depth,
-            mono_chrome: false, color_primaries: 2, transfer_characteristics: 2,
-            matrix_coefficients: 2, subsampling_x: true, subsampling_y: true,
-            use_128x128_superblock: false, enable_filter_intra: false,
-            enable_intra_edge_filter: false, enable_interintra_compound: false,
-            enable_masked_compound: false, enable_warped_motion: false,
-            enable_dual_filter: false, enable_order_hint: true,
-            enable_jnt_comp: false, enable_ref_frame_mvs: false,
-            order_hint_bits: 7, enable_superres: false,
-            enable_cdef: true, enable_restoration: false,
-        });
-        Ok(())
+
+    /// Follow the sequence header's dimensions and format
+    fn update_state(&mut self) {
+        if let Some(seq) = self.inner.sequence_header() {
+            self.state.width = seq.max_frame_width;
+            self.state.height = seq.max_frame_height;
+            self.state.format = if seq.bit_depth > 8 { PixelFormat::I420_10 } else { PixelFormat::I420 };
+        }
     }
-    
-    fn decode_frame(&mut self, _data: &[u8], is_key: bool, pts: Duration, dts: Duration) -> DecoderResult<Option<VideoFrame>> {
-        let (w, h) = (self.state.width, self.state.height);
-        if w == 0 || h == 0 { return Err(DecoderError::InvalidBitstream("No sequence header".into())); }
-        
-        let bit_depth = self.seq_header.as_ref().map(|s| s.bit_depth).unwrap_or(8);
-        let format = if bit_depth > 8 { PixelFormat::I420_10 } else { PixelFormat::I420 };
-        
-        let y_size = (w * h) as usize;
-        let uv_size = y_size / 4;
-        
-        let frame = VideoFrame {
-            pts, dts, duration: Duration::from_millis(33), width: w, height: h,
-            format, key_frame: is_key,
-            planes: vec![
-                Plane { data: vec![128; y_size], stride: w as usize },
-                Plane { data: vec![128; uv_size], stride: (w/2) as usize },
-                Plane { data: vec![128; uv_size], stride: (w/2) as usize },
-            ],
+
+    /// Pack a picture into an output frame
+    fn output_frame(&mut self, picture: &Picture, packet: &EncodedPacket) -> DecoderResult<VideoFrame> {
+        let (w, h) = (picture.width as usize, picture.height as usize);
+        let high = picture.bit_depth > 8;
+        let format = match (high, picture.ss_x, picture.ss_y) {
+            (false, true, true) => PixelFormat::I420,
+            (false, true, false) => PixelFormat::I422,
+            (false, false, false) => PixelFormat::I444,
+            (true, true, true) => PixelFormat::I420_10,
+            _ => return Err(DecoderError::Unsupported("AV1 high bit depth 4:2:2/4:4:4 output".into())),
         };
-        
-        if is_key { for rf in &mut self.ref_frames { *rf = Some(frame.clone()); } }
-        
-        Ok(Some(frame))
+        let bytes = if high { 2 } else { 1 };
+        let (cw, ch) = ((w + picture.ss_x as usize) >> picture.ss_x as usize, (h + picture.ss_y as usize) >> picture.ss_y as usize);
+        let mut planes = Vec::with_capacity(3);
+        for plane in 0..3 {
+            let (pw, ph) = if plane == 0 { (w, h) } else { (cw, ch) };
+            // Monochrome streams get neutral chroma
+            let neutral = 1u16 << (picture.bit_depth - 1);
+            let samples = picture.planes.get(plane);
+            let mut data = Vec::with_capacity(pw * ph * bytes);
+            for i in 0..pw * ph {
+                let v = samples.map_or(neutral, |s| s[i]);
+                if high {
+                    data.extend_from_slice(&v.to_le_bytes());
+                } else {
+                    data.push(v as u8);
+                }
+            }
+            planes.push(Plane { data, stride: pw * bytes });
+        }
+        self.state.width = w as u32;
+        self.state.height = h as u32;
+        self.state.format = format;
+        self.state.frame_num += 1;
+        Ok(VideoFrame {
+            pts: packet.pts,
+            dts: packet.dts,
+            duration: Duration::from_millis(33),
+            width: w as u32,
+            height: h as u32,
+            format,
+            planes,
+            key_frame: picture.key_frame,
+            surface: None,
+        })
     }
 }
 
-impl Default for Av1Decoder { fn default() -> Self { Self::new() } }
+impl Default for Av1Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl VideoDecoderTrait for Av1Decoder {
     fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<Vec<VideoFrame>> {
-        let mut output = Vec::new();
-        let mut saw_key = false;
-        
-        for (obu_type, data) in self.parse_obus(&packet.data) {
-            match obu_type {
-                ObuType::SequenceHeader => { self.parse_sequence_header(&data)?; }
-                ObuType::FrameHeader | ObuType::Frame => {
-                    let is_key = packet.is_key || saw_key;
-                    if obu_type == ObuType::Frame && !data.is_empty() && (data[0] & 0x60) == 0 { saw_key = true; }
-                    if let Some(f) = self.decode_frame(&data, is_key, packet.pts, packet.dts)? { output.push(f); }
-                }
-                _ => {}
-            }
-        }
-        Ok(output)
+        let pictures = self.inner.decode(&packet.data)?;
+        self.update_state();
+        pictures.iter().map(|picture| self.output_frame(picture, packet)).collect()
+    }
+
+    fn flush(&mut self) -> Vec<VideoFrame> {
+        self.inner.flush();
+        Vec::new()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.state = VideoDecoderState::default();
+    }
+
+    fn capabilities(&self) -> DecoderCaps {
+        DecoderCaps { max_width: 8192, max_height: 4320, formats: vec![PixelFormat::I420, PixelFormat::I420_10], hardware: false }
+    }
+
+    /// Read the configuration OBUs of an `av1C` record
+    fn configure(&mut self, extradata: &[u8]) -> DecoderResult<()> {
+        self.inner.configure(extradata)?;
+        self.update_state();
+        Ok(())
     }
-    fn flush(&mut self) -> Vec<VideoFrame> { std::mem::take(&mut self.pending_output) }
-    fn reset(&mut self) { self.ref_frames = Default::default(); self.seq_header = None; self.state = VideoDecoderState::default(); }
-    fn capabilities(&self) -> DecoderCaps { DecoderCaps { max_width: 8192, max_height: 4320, formats: vec![PixelFormat::I420, PixelFormat::I420_10], hardware: false } }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
     #[test]
-    fn test_decoder() { let d = Av1Decoder::new(); assert_eq!(d.capabilities().max_width, 8192); }
+    fn test_decoder() {
+        let d = Av1Decoder::new();
+        assert_eq!(d.capabilities().max_width, 8192);
+    }
+
+    #[test]
+    fn test_decode_key_and_inter_frame() {
+        let mut d = Av1Decoder::with_threads(2);
+        // Plane sums of the encoder's reconstruction
+        let expected = [[19402, 7484, 7740], [17556, 7262, 7518]];
+        for (data, sums) in TEST_STREAM.iter().zip(expected) {
+            let packet = EncodedPacket { data: data.to_vec(), pts: Duration::ZERO, dts: Duration::ZERO, is_key: false };
+            let frames = d.decode(&packet).unwrap();
+            assert_eq!(frames.len(), 1);
+            let frame = &frames[0];
+            assert_eq!((frame.width, frame.height, frame.format), (16, 16, PixelFormat::I420));
+            let got: Vec<u32> = frame.planes.iter().map(|p| p.data.iter().map(|&v| v as u32).sum()).collect();
+            assert_eq!(got, sums);
+        }
+        assert_eq!(d.sequence_header().map(|s| s.bit_depth), Some(8));
+    }
 }
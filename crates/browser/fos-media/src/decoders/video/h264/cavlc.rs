@@ -0,0 +1,297 @@
+//! H.264 CAVLC Entropy Decoding
+//!
+//! Context-adaptive variable length coding of residual blocks (clause 9.2).
+
+use super::super::BitReader;
+use crate::decoders::{DecoderError, DecoderResult};
+
+/// coeff_token code lengths, indexed `total_coeff * 4 + trailing_ones` (0 <= nC < 2)
+const COEFF_TOKEN_LEN_0: [u8; 68] = [
+    1, 0, 0, 0, 6, 2, 0, 0, 8, 6, 3, 0, 9, 8, 7, 5, 10, 9, 8, 6, 11, 10, 9, 7,
+    13, 11, 10, 8, 13, 13, 11, 9, 13, 13, 13, 10, 14, 14, 13, 11, 14, 14, 14, 13,
+    15, 15, 14, 14, 15, 15, 15, 14, 16, 15, 15, 15, 16, 16, 16, 15, 16, 16, 16, 16,
+    16, 16, 16, 16,
+];
+const COEFF_TOKEN_BITS_0: [u8; 68] = [
+    1, 0, 0, 0, 5, 1, 0, 0, 7, 4, 1, 0, 7, 6, 5, 3, 7, 6, 5, 3, 7, 6, 5, 4,
+    15, 6, 5, 4, 11, 14, 5, 4, 8, 10, 13, 4, 15, 14, 9, 4, 11, 10, 13, 12,
+    15, 14, 9, 12, 11, 10, 13, 8, 15, 1, 9, 12, 11, 14, 13, 8, 7, 10, 9, 12,
+    4, 6, 5, 8,
+];
+
+/// 2 <= nC < 4
+const COEFF_TOKEN_LEN_1: [u8; 68] = [
+    2, 0, 0, 0, 6, 2, 0, 0, 6, 5, 3, 0, 7, 6, 6, 4, 8, 6, 6, 4, 8, 7, 7, 5,
+    9, 8, 8, 6, 11, 9, 9, 6, 11, 11, 11, 7, 12, 11, 11, 9, 12, 12, 12, 11,
+    12, 12, 12, 11, 13, 13, 13, 12, 13, 13, 13, 13, 13, 14, 13, 13, 14, 14, 14, 13,
+    14, 14, 14, 14,
+];
+const COEFF_TOKEN_BITS_1: [u8; 68] = [
+    3, 0, 0, 0, 11, 2, 0, 0, 7, 7, 3, 0, 7, 10, 9, 5, 7, 6, 5, 4, 4, 6, 5, 6,
+    7, 6, 5, 8, 15, 6, 5, 4, 11, 14, 13, 4, 15, 10, 9, 4, 11, 14, 13, 12,
+    8, 10, 9, 8, 15, 14, 13, 12, 11, 10, 9, 12, 7, 11, 6, 8, 9, 8, 10, 1,
+    7, 6, 5, 4,
+];
+
+/// 4 <= nC < 8
+const COEFF_TOKEN_LEN_2: [u8; 68] = [
+    4, 0, 0, 0, 6, 4, 0, 0, 6, 5, 4, 0, 6, 5, 5, 4, 7, 5, 5, 4, 7, 5, 5, 4,
+    7, 6, 6, 4, 7, 6, 6, 4, 8, 7, 7, 5, 8, 8, 7, 6, 9, 8, 8, 7,
+    9, 9, 8, 8, 9, 9, 9, 8, 10, 9, 9, 9, 10, 10, 10, 10, 10, 10, 10, 10,
+    10, 10, 10, 10,
+];
+const COEFF_TOKEN_BITS_2: [u8; 68] = [
+    15, 0, 0, 0, 15, 14, 0, 0, 11, 15, 13, 0, 8, 12, 14, 12, 15, 10, 11, 11, 11, 8, 9, 10,
+    9, 14, 13, 9, 8, 10, 9, 8, 15, 14, 13, 13, 11, 14, 10, 12, 15, 10, 13, 12,
+    11, 14, 9, 12, 8, 10, 13, 8, 13, 7, 9, 12, 9, 12, 11, 10, 5, 8, 7, 6,
+    1, 4, 3, 2,
+];
+
+/// nC == -1 (chroma DC, 4:2:0)
+const COEFF_TOKEN_LEN_CHROMA_DC: [u8; 20] = [2, 0, 0, 0, 6, 1, 0, 0, 6, 6, 3, 0, 6, 7, 7, 6, 6, 8, 8, 7];
+const COEFF_TOKEN_BITS_CHROMA_DC: [u8; 20] = [1, 0, 0, 0, 7, 1, 0, 0, 4, 6, 1, 0, 3, 3, 2, 5, 2, 3, 2, 0];
+
+/// total_zeros for 4x4 blocks, indexed `[total_coeff - 1][total_zeros]`
+const TOTAL_ZEROS_LEN: [[u8; 16]; 15] = [
+    [1, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 9],
+    [3, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 6, 6, 6, 6, 0],
+    [4, 3, 3, 3, 4, 4, 3, 3, 4, 5, 5, 6, 5, 6, 0, 0],
+    [5, 3, 4, 4, 3, 3, 3, 4, 3, 4, 5, 5, 5, 0, 0, 0],
+    [4, 4, 4, 3, 3, 3, 3, 3, 4, 5, 4, 5, 0, 0, 0, 0],
+    [6, 5, 3, 3, 3, 3, 3, 3, 4, 3, 6, 0, 0, 0, 0, 0],
+    [6, 5, 3, 3, 3, 2, 3, 4, 3, 6, 0, 0, 0, 0, 0, 0],
+    [6, 4, 5, 3, 2, 2, 3, 3, 6, 0, 0, 0, 0, 0, 0, 0],
+    [6, 6, 4, 2, 2, 3, 2, 5, 0, 0, 0, 0, 0, 0, 0, 0],
+    [5, 5, 3, 2, 2, 2, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [4, 4, 3, 3, 1, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [4, 4, 2, 1, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 3, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [2, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+];
+const TOTAL_ZEROS_BITS: [[u8; 16]; 15] = [
+    [1, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 1],
+    [7, 6, 5, 4, 3, 5, 4, 3, 2, 3, 2, 3, 2, 1, 0, 0],
+    [5, 7, 6, 5, 4, 3, 4, 3, 2, 3, 2, 1, 1, 0, 0, 0],
+    [3, 7, 5, 4, 6, 5, 4, 3, 3, 2, 2, 1, 0, 0, 0, 0],
+    [5, 4, 3, 7, 6, 5, 4, 3, 2, 1, 1, 0, 0, 0, 0, 0],
+    [1, 1, 7, 6, 5, 4, 3, 2, 1, 1, 0, 0, 0, 0, 0, 0],
+    [1, 1, 5, 4, 3, 3, 2, 1, 1, 0, 0, 0, 0, 0, 0, 0],
+    [1, 1, 1, 3, 3, 2, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+    [1, 0, 1, 3, 2, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+    [1, 0, 1, 3, 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 2, 1, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+];
+
+/// total_zeros for chroma DC 2x2 blocks
+const CHROMA_DC_TOTAL_ZEROS_LEN: [[u8; 4]; 3] = [[1, 2, 3, 3], [1, 2, 2, 0], [1, 1, 0, 0]];
+const CHROMA_DC_TOTAL_ZEROS_BITS: [[u8; 4]; 3] = [[1, 1, 1, 0], [1, 1, 0, 0], [1, 0, 0, 0]];
+
+/// run_before, indexed `[min(zeros_left, 7) - 1][run_before]`
+const RUN_BEFORE_LEN: [[u8; 15]; 7] = [
+    [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [1, 2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [2, 2, 2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [2, 2, 2, 3, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [2, 2, 3, 3, 3, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [2, 3, 3, 3, 3, 3, 3, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 3, 3, 3, 3, 3, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+];
+const RUN_BEFORE_BITS: [[u8; 15]; 7] = [
+    [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 2, 3, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 0, 1, 3, 2, 5, 4, 0, 0, 0, 0, 0, 0, 0, 0],
+    [7, 6, 5, 4, 3, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+];
+
+/// coded_block_pattern mapping for me(v), Intra_4x4 macroblocks
+pub const INTRA_CBP: [u8; 48] = [
+    47, 31, 15, 0, 23, 27, 29, 30, 7, 11, 13, 14, 39, 43, 45, 46,
+    16, 3, 5, 10, 12, 19, 21, 26, 28, 35, 37, 42, 44, 1, 2, 4,
+    8, 17, 18, 20, 24, 6, 9, 22, 25, 32, 33, 34, 36, 40, 38, 41,
+];
+
+/// coded_block_pattern mapping for me(v), inter macroblocks
+pub const INTER_CBP: [u8; 48] = [
+    0, 16, 1, 2, 4, 8, 32, 3, 5, 10, 12, 15, 47, 7, 11, 13,
+    14, 6, 9, 31, 35, 37, 42, 44, 33, 34, 36, 40, 39, 43, 45, 46,
+    17, 18, 20, 24, 19, 21, 26, 28, 23, 27, 29, 30, 22, 25, 38, 41,
+];
+
+/// Read a prefix code, returning the index of the matching table entry
+fn read_vlc(r: &mut BitReader, lens: &[u8], codes: &[u8]) -> DecoderResult<usize> {
+    let max_len = lens.iter().copied().max().unwrap_or(0);
+    let mut code = 0u32;
+    for len in 1..=max_len {
+        code = (code << 1) | r.read_bits(1)?;
+        if let Some(i) = (0..lens.len()).find(|&i| lens[i] == len && codes[i] as u32 == code) {
+            return Ok(i);
+        }
+    }
+    Err(DecoderError::InvalidBitstream("Invalid CAVLC code".into()))
+}
+
+/// Read coeff_token, returning `(total_coeff, trailing_ones)`
+pub fn read_coeff_token(r: &mut BitReader, nc: i32) -> DecoderResult<(usize, usize)> {
+    let idx = match nc {
+        -1 => read_vlc(r, &COEFF_TOKEN_LEN_CHROMA_DC, &COEFF_TOKEN_BITS_CHROMA_DC)?,
+        0..=1 => read_vlc(r, &COEFF_TOKEN_LEN_0, &COEFF_TOKEN_BITS_0)?,
+        2..=3 => read_vlc(r, &COEFF_TOKEN_LEN_1, &COEFF_TOKEN_BITS_1)?,
+        4..=7 => read_vlc(r, &COEFF_TOKEN_LEN_2, &COEFF_TOKEN_BITS_2)?,
+        _ => {
+            // 6-bit fixed length code
+            let code = r.read_bits(6)? as usize;
+            if code == 3 {
+                return Ok((0, 0));
+            }
+            let (total, ones) = ((code >> 2) + 1, code & 3);
+            if ones > total {
+                return Err(DecoderError::InvalidBitstream("Invalid coeff_token".into()));
+            }
+            return Ok((total, ones));
+        }
+    };
+    Ok((idx / 4, idx % 4))
+}
+
+/// Parse one residual block (clause 9.2). Returns the number of non-zero
+/// coefficients; `coeffs[start..start + max_coeff]` receives levels in scan order.
+pub fn read_residual_block(
+    r: &mut BitReader,
+    nc: i32,
+    max_coeff: usize,
+    coeffs: &mut [i32],
+    start: usize,
+) -> DecoderResult<usize> {
+    let (total_coeff, trailing_ones) = read_coeff_token(r, nc)?;
+    if total_coeff == 0 {
+        return Ok(0);
+    }
+    if total_coeff > max_coeff {
+        return Err(DecoderError::InvalidBitstream("Too many coefficients".into()));
+    }
+
+    // Levels, highest frequency first
+    let mut levels = [0i32; 16];
+    let mut suffix_length = if total_coeff > 10 && trailing_ones < 3 { 1 } else { 0 };
+    for (i, level) in levels.iter_mut().enumerate().take(total_coeff) {
+        if i < trailing_ones {
+            *level = if r.read_bit()? { -1 } else { 1 };
+            continue;
+        }
+        let mut level_prefix = 0u32;
+        while !r.read_bit()? {
+            level_prefix += 1;
+            if level_prefix > 32 {
+                return Err(DecoderError::InvalidBitstream("level_prefix overflow".into()));
+            }
+        }
+        let mut level_code = (level_prefix.min(15) << suffix_length) as i32;
+        let suffix_size = if level_prefix == 14 && suffix_length == 0 {
+            4
+        } else if level_prefix >= 15 {
+            level_prefix - 3
+        } else {
+            suffix_length
+        };
+        if suffix_size > 0 {
+            level_code += r.read_bits(suffix_size as u8)? as i32;
+        }
+        if level_prefix >= 15 && suffix_length == 0 {
+            level_code += 15;
+        }
+        if level_prefix >= 16 {
+            level_code += (1 << (level_prefix - 3)) - 4096;
+        }
+        if i == trailing_ones && trailing_ones < 3 {
+            level_code += 2;
+        }
+        *level = if level_code % 2 == 0 { (level_code + 2) >> 1 } else { (-level_code - 1) >> 1 };
+
+        if suffix_length == 0 {
+            suffix_length = 1;
+        }
+        if level.abs() > (3 << (suffix_length - 1)) && suffix_length < 6 {
+            suffix_length += 1;
+        }
+    }
+
+    let mut zeros_left = if total_coeff < max_coeff {
+        let tc = total_coeff - 1;
+        if max_coeff == 4 {
+            read_vlc(r, &CHROMA_DC_TOTAL_ZEROS_LEN[tc], &CHROMA_DC_TOTAL_ZEROS_BITS[tc])?
+        } else {
+            read_vlc(r, &TOTAL_ZEROS_LEN[tc], &TOTAL_ZEROS_BITS[tc])?
+        }
+    } else {
+        0
+    };
+    if zeros_left + total_coeff > max_coeff {
+        return Err(DecoderError::InvalidBitstream("Invalid total_zeros".into()));
+    }
+
+    // Place levels from the lowest frequency upwards
+    let mut runs = [0usize; 16];
+    for run in runs.iter_mut().take(total_coeff - 1) {
+        if zeros_left == 0 {
+            break;
+        }
+        let table = zeros_left.min(7) - 1;
+        *run = read_vlc(r, &RUN_BEFORE_LEN[table], &RUN_BEFORE_BITS[table])?;
+        if *run > zeros_left {
+            return Err(DecoderError::InvalidBitstream("Invalid run_before".into()));
+        }
+        zeros_left -= *run;
+    }
+    runs[total_coeff - 1] = zeros_left;
+
+    let mut pos = 0;
+    for i in (0..total_coeff).rev() {
+        pos += runs[i];
+        coeffs[start + pos] = levels[i];
+        pos += 1;
+    }
+    Ok(total_coeff)
+}
+
+/// Read te(v) with the given maximum value
+pub fn read_te(r: &mut BitReader, max: u32) -> DecoderResult<u32> {
+    if max == 1 {
+        Ok(if r.read_bit()? { 0 } else { 1 })
+    } else {
+        r.read_ue()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coeff_token() {
+        // '1' -> (0, 0), '01' -> (1, 1), '001' -> (2, 2) for nC = 0
+        let data = [0b1010_0100];
+        let mut r = BitReader::new(&data);
+        assert_eq!(read_coeff_token(&mut r, 0).unwrap(), (0, 0));
+        assert_eq!(read_coeff_token(&mut r, 0).unwrap(), (1, 1));
+        assert_eq!(read_coeff_token(&mut r, 0).unwrap(), (2, 2));
+    }
+
+    #[test]
+    fn test_residual_block() {
+        // coeff_token (1, 1) = '01', sign '0' (+1), total_zeros(tc=1) = 2 -> '010'
+        let data = [0b0100_1000];
+        let mut r = BitReader::new(&data);
+        let mut coeffs = [0i32; 16];
+        assert_eq!(read_residual_block(&mut r, 0, 16, &mut coeffs, 0).unwrap(), 1);
+        assert_eq!(coeffs[2], 1);
+        assert_eq!(coeffs.iter().filter(|&&c| c != 0).count(), 1);
+    }
+}
@@ -0,0 +1,245 @@
+//! H.264 Deblocking Filter
+//!
+//! In-loop filtering of 4x4 block edges after a picture is reconstructed (clause 8.7).
+
+use super::macroblock::{MbInfo, PictureState};
+use super::recon::clip_pixel;
+
+const ALPHA: [u8; 52] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 4, 5, 6, 7, 8, 9, 10, 12, 13,
+    15, 17, 20, 22, 25, 28, 32, 36, 40, 45, 50, 56, 63, 71, 80, 90, 101, 113, 127, 144, 162, 182, 203, 226, 255, 255,
+];
+
+const BETA: [u8; 52] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4,
+    6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13, 14, 14, 15, 15, 16, 16, 17, 17, 18, 18,
+];
+
+/// tC0 for bS = 1, 2, 3
+const TC0: [[u8; 3]; 52] = [
+    [0, 0, 0], [0, 0, 0], [0, 0, 0], [0, 0, 0], [0, 0, 0], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+    [0, 0, 0], [0, 0, 0], [0, 0, 0], [0, 0, 0], [0, 0, 0], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+    [0, 0, 0], [0, 0, 1], [0, 0, 1], [0, 0, 1], [0, 0, 1], [0, 1, 1], [0, 1, 1], [1, 1, 1],
+    [1, 1, 1], [1, 1, 1], [1, 1, 1], [1, 1, 2], [1, 1, 2], [1, 1, 2], [1, 1, 2], [1, 2, 3],
+    [1, 2, 3], [2, 2, 3], [2, 2, 4], [2, 3, 4], [2, 3, 4], [3, 3, 5], [3, 4, 6], [3, 4, 6],
+    [4, 5, 7], [4, 5, 8], [4, 6, 9], [5, 7, 10], [6, 8, 11], [6, 8, 13], [7, 10, 14], [8, 11, 16],
+    [9, 12, 18], [10, 13, 20], [11, 15, 23], [13, 17, 25],
+];
+
+/// Slice-level deblocking controls
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeblockParams {
+    /// disable_deblocking_filter_idc
+    pub disable: u32,
+    /// FilterOffsetA
+    pub alpha_offset: i32,
+    /// FilterOffsetB
+    pub beta_offset: i32,
+}
+
+/// Boundary strength between two 4x4 luma blocks
+fn boundary_strength(p: &MbInfo, pb: usize, q: &MbInfo, qb: usize, mb_edge: bool) -> u8 {
+    if p.kind.is_intra() || q.kind.is_intra() {
+        return if mb_edge { 4 } else { 3 };
+    }
+    if p.total_coeff[pb] > 0 || q.total_coeff[qb] > 0 {
+        return 2;
+    }
+    let p8 = (pb % 4) / 2 + 2 * (pb / 8);
+    let q8 = (qb % 4) / 2 + 2 * (qb / 8);
+    let (pmv, qmv) = (p.mv[pb], q.mv[qb]);
+    if p.ref_poc[p8] != q.ref_poc[q8]
+        || (pmv[0] as i32 - qmv[0] as i32).abs() >= 4
+        || (pmv[1] as i32 - qmv[1] as i32).abs() >= 4
+    {
+        return 1;
+    }
+    0
+}
+
+/// Filter one line of samples across an edge. `samples[3]` is p0, `samples[4]` is q0.
+fn filter_line(s: &mut [i32; 8], bs: u8, index_a: usize, alpha: i32, beta: i32, chroma: bool) {
+    let (p0, p1, p2, p3) = (s[3], s[2], s[1], s[0]);
+    let (q0, q1, q2, q3) = (s[4], s[5], s[6], s[7]);
+    if (p0 - q0).abs() >= alpha || (p1 - p0).abs() >= beta || (q1 - q0).abs() >= beta {
+        return;
+    }
+    let ap = (p2 - p0).abs();
+    let aq = (q2 - q0).abs();
+    if bs < 4 {
+        let tc0 = TC0[index_a][bs as usize - 1] as i32;
+        let tc = if chroma { tc0 + 1 } else { tc0 + (ap < beta) as i32 + (aq < beta) as i32 };
+        let delta = ((((q0 - p0) << 2) + (p1 - q1) + 4) >> 3).clamp(-tc, tc);
+        s[3] = clip_pixel(p0 + delta) as i32;
+        s[4] = clip_pixel(q0 - delta) as i32;
+        if !chroma {
+            if ap < beta {
+                s[2] = p1 + ((p2 + ((p0 + q0 + 1) >> 1) - (p1 << 1)) >> 1).clamp(-tc0, tc0);
+            }
+            if aq < beta {
+                s[5] = q1 + ((q2 + ((p0 + q0 + 1) >> 1) - (q1 << 1)) >> 1).clamp(-tc0, tc0);
+            }
+        }
+    } else if chroma {
+        s[3] = (2 * p1 + p0 + q1 + 2) >> 2;
+        s[4] = (2 * q1 + q0 + p1 + 2) >> 2;
+    } else {
+        let strong = (p0 - q0).abs() < ((alpha >> 2) + 2);
+        if ap < beta && strong {
+            s[3] = (p2 + 2 * p1 + 2 * p0 + 2 * q0 + q1 + 4) >> 3;
+            s[2] = (p2 + p1 + p0 + q0 + 2) >> 2;
+            s[1] = (2 * p3 + 3 * p2 + p1 + p0 + q0 + 4) >> 3;
+        } else {
+            s[3] = (2 * p1 + p0 + q1 + 2) >> 2;
+        }
+        if aq < beta && strong {
+            s[4] = (p1 + 2 * p0 + 2 * q0 + 2 * q1 + q2 + 4) >> 3;
+            s[5] = (p0 + q0 + q1 + q2 + 2) >> 2;
+            s[6] = (2 * q3 + 3 * q2 + q1 + q0 + p0 + 4) >> 3;
+        } else {
+            s[4] = (2 * q1 + q0 + p1 + 2) >> 2;
+        }
+    }
+}
+
+/// Filter the sample lines across an edge starting at (x, y). Vertical
+/// edges step along y and filter horizontally; horizontal edges the reverse.
+#[allow(clippy::too_many_arguments)]
+fn filter_edge(
+    data: &mut [u8], stride: usize, x: usize, y: usize, vertical: bool,
+    bs: &[u8], lines_per_bs: usize, qp: i32, params: &DeblockParams, chroma: bool,
+) {
+    let index_a = (qp + params.alpha_offset).clamp(0, 51) as usize;
+    let index_b = (qp + params.beta_offset).clamp(0, 51) as usize;
+    let (alpha, beta) = (ALPHA[index_a] as i32, BETA[index_b] as i32);
+    if alpha == 0 || beta == 0 {
+        return;
+    }
+    let taps = if chroma { 2 } else { 4 };
+    for (i, &strength) in bs.iter().enumerate() {
+        if strength == 0 {
+            continue;
+        }
+        for line in i * lines_per_bs..(i + 1) * lines_per_bs {
+            let idx = |k: i32| -> usize {
+                if vertical {
+                    (y + line) * stride + (x as i32 + k) as usize
+                } else {
+                    ((y as i32 + k) as usize) * stride + x + line
+                }
+            };
+            let mut s = [0i32; 8];
+            for k in -taps..taps {
+                s[(k + 4) as usize] = data[idx(k)] as i32;
+            }
+            if chroma {
+                // Pad so p1/q1 indexing works with two samples per side
+                s[0] = s[2];
+                s[1] = s[2];
+                s[6] = s[5];
+                s[7] = s[5];
+            }
+            filter_line(&mut s, strength, index_a, alpha, beta, chroma);
+            for k in -taps..taps {
+                data[idx(k)] = s[(k + 4) as usize] as u8;
+            }
+        }
+    }
+}
+
+/// Run the deblocking filter over a fully reconstructed picture
+pub fn deblock_picture(pic: &mut PictureState, params: &[DeblockParams]) {
+    let (mbw, mbh) = (pic.mb_width, pic.mb_height);
+    for mb_y in 0..mbh {
+        for mb_x in 0..mbw {
+            let q = pic.mbs[mb_y * mbw + mb_x];
+            let Some(slice) = q.slice else { continue };
+            let slice_params = params.get(slice as usize).copied().unwrap_or_default();
+            if slice_params.disable == 1 {
+                continue;
+            }
+            let usable = |m: &MbInfo| m.slice.is_some() && (slice_params.disable != 2 || m.slice == Some(slice));
+
+            for vertical in [true, false] {
+                let neighbor = if vertical {
+                    (mb_x > 0).then(|| pic.mbs[mb_y * mbw + mb_x - 1])
+                } else {
+                    (mb_y > 0).then(|| pic.mbs[(mb_y - 1) * mbw + mb_x])
+                };
+                let neighbor = neighbor.filter(|m| usable(m));
+                for edge in 0..4 {
+                    let p = if edge == 0 {
+                        match neighbor {
+                            Some(ref n) => n,
+                            None => continue,
+                        }
+                    } else {
+                        &q
+                    };
+                    let mut bs = [0u8; 4];
+                    for (i, b) in bs.iter_mut().enumerate() {
+                        let (qb, pb) = if vertical {
+                            (edge + 4 * i, if edge == 0 { 3 + 4 * i } else { edge - 1 + 4 * i })
+                        } else {
+                            (i + 4 * edge, if edge == 0 { i + 12 } else { i + 4 * (edge - 1) })
+                        };
+                        *b = boundary_strength(p, pb, &q, qb, edge == 0);
+                    }
+                    if bs.iter().all(|&b| b == 0) {
+                        continue;
+                    }
+
+                    // Luma
+                    let qp = (p.qp + q.qp + 1) >> 1;
+                    let (x, y) = if vertical {
+                        (mb_x * 16 + edge * 4, mb_y * 16)
+                    } else {
+                        (mb_x * 16, mb_y * 16 + edge * 4)
+                    };
+                    let luma = &mut pic.frame.planes[0];
+                    filter_edge(&mut luma.data, luma.stride, x, y, vertical, &bs, 4, qp, &slice_params, false);
+
+                    // Chroma edges fall on luma edges 0 and 2
+                    if edge % 2 == 0 {
+                        let qpc = (p.qpc + q.qpc + 1) >> 1;
+                        let (cx, cy) = if vertical {
+                            (mb_x * 8 + edge * 2, mb_y * 8)
+                        } else {
+                            (mb_x * 8, mb_y * 8 + edge * 2)
+                        };
+                        for plane in 1..3 {
+                            let chroma = &mut pic.frame.planes[plane];
+                            filter_edge(&mut chroma.data, chroma.stride, cx, cy, vertical, &bs, 2, qpc, &slice_params, true);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::macroblock::MbKind;
+
+    #[test]
+    fn test_boundary_strength() {
+        let intra = MbInfo { kind: MbKind::Intra4x4, ..MbInfo::default() };
+        let inter = MbInfo { kind: MbKind::Inter, ..MbInfo::default() };
+        assert_eq!(boundary_strength(&intra, 3, &inter, 0, true), 4);
+        assert_eq!(boundary_strength(&intra, 0, &intra, 1, false), 3);
+        assert_eq!(boundary_strength(&inter, 0, &inter, 1, false), 0);
+
+        let mut moved = inter;
+        moved.mv[1] = [4, 0];
+        assert_eq!(boundary_strength(&inter, 0, &moved, 1, false), 1);
+    }
+
+    #[test]
+    fn test_strong_filter_smooths_edge() {
+        let mut s = [60, 60, 60, 60, 70, 70, 70, 70];
+        filter_line(&mut s, 4, 40, ALPHA[40] as i32, BETA[40] as i32, false);
+        assert!(s[3] > 60 && s[4] < 70);
+    }
+}
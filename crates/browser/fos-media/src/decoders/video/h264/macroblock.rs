@@ -0,0 +1,672 @@
+//! H.264 Macroblock Layer
+//!
+//! CAVLC slice data parsing and macroblock reconstruction for I and P slices
+//! (clauses 7.3.4, 7.3.5 and 8.4.1).
+
+use super::super::BitReader;
+use super::cavlc::{read_residual_block, read_te, INTER_CBP, INTRA_CBP};
+use super::recon::{
+    chroma_dc_dequant, chroma_qp, dequant_4x4, idct_add_4x4, luma_dc_dequant, predict_chroma,
+    predict_chroma_mc, predict_intra16x16, predict_intra4x4, predict_luma, IntraNeighbors, RefPlane,
+};
+use crate::decoders::{DecoderError, DecoderResult, VideoFrame};
+
+/// Raster index (x + 4y) of each 4x4 luma block in decoding order.
+/// The mapping is its own inverse, so it also gives the decoding order of a raster block.
+pub const BLOCK_RASTER: [usize; 16] = [0, 1, 4, 5, 2, 3, 6, 7, 8, 9, 12, 13, 10, 11, 14, 15];
+
+/// Macroblock prediction kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MbKind {
+    Intra4x4,
+    Intra16x16,
+    IPcm,
+    Inter,
+    Skip,
+}
+
+impl MbKind {
+    pub fn is_intra(self) -> bool {
+        matches!(self, Self::Intra4x4 | Self::Intra16x16 | Self::IPcm)
+    }
+}
+
+/// Per-macroblock state kept for neighbour prediction and deblocking
+#[derive(Debug, Clone, Copy)]
+pub struct MbInfo {
+    /// Slice the macroblock was decoded in (`None` until decoded)
+    pub slice: Option<u32>,
+    pub kind: MbKind,
+    /// Luma QP (0 for I_PCM)
+    pub qp: i32,
+    /// Chroma QP
+    pub qpc: i32,
+    /// Non-zero coefficients per 4x4 luma block (raster)
+    pub total_coeff: [u8; 16],
+    /// Non-zero AC coefficients per 4x4 chroma block, Cb then Cr
+    pub total_coeff_chroma: [[u8; 4]; 2],
+    /// Intra4x4PredMode per 4x4 block (2 = DC for other kinds)
+    pub intra_modes: [u8; 16],
+    /// Reference index per 8x8 block (-1 = intra)
+    pub ref_idx: [i8; 4],
+    /// POC of the referenced picture per 8x8 block
+    pub ref_poc: [i32; 4],
+    /// Motion vector per 4x4 block in quarter samples
+    pub mv: [[i16; 2]; 16],
+}
+
+impl Default for MbInfo {
+    fn default() -> Self {
+        Self {
+            slice: None,
+            kind: MbKind::Skip,
+            qp: 0,
+            qpc: 0,
+            total_coeff: [0; 16],
+            total_coeff_chroma: [[0; 4]; 2],
+            intra_modes: [2; 16],
+            ref_idx: [-1; 4],
+            ref_poc: [0; 4],
+            mv: [[0; 2]; 16],
+        }
+    }
+}
+
+/// Picture being decoded (macroblock-aligned planes)
+#[derive(Debug)]
+pub struct PictureState {
+    pub frame: VideoFrame,
+    pub mbs: Vec<MbInfo>,
+    pub mb_width: usize,
+    pub mb_height: usize,
+}
+
+impl PictureState {
+    pub fn new(frame: VideoFrame, mb_width: usize, mb_height: usize) -> Self {
+        Self { frame, mbs: vec![MbInfo::default(); mb_width * mb_height], mb_width, mb_height }
+    }
+}
+
+/// Entry of the reference picture list
+#[derive(Debug, Clone, Copy)]
+pub struct RefPic<'a> {
+    pub frame: &'a VideoFrame,
+    pub poc: i32,
+}
+
+/// Slice-wide decoding parameters
+#[derive(Debug)]
+pub struct SliceContext<'a> {
+    pub slice_num: u32,
+    pub is_p: bool,
+    /// Running QPY
+    pub qp: i32,
+    pub chroma_qp_offset: i32,
+    pub constrained_intra_pred: bool,
+    pub num_ref_idx_active: u32,
+    pub refs: Vec<RefPic<'a>>,
+}
+
+/// Inter prediction partition in 4x4 block units
+#[derive(Debug, Clone, Copy)]
+struct Partition {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+    ref_idx: i8,
+    mvd: [i32; 2],
+}
+
+type Motion = (i8, [i16; 2]);
+
+/// Residual coefficients of one macroblock, in scan order
+struct Residual {
+    luma_dc: [i32; 16],
+    luma: [[i32; 16]; 16],
+    chroma_dc: [[i32; 4]; 2],
+    chroma_ac: [[[i32; 16]; 4]; 2],
+}
+
+impl Residual {
+    fn new() -> Self {
+        Self { luma_dc: [0; 16], luma: [[0; 16]; 16], chroma_dc: [[0; 4]; 2], chroma_ac: [[[0; 16]; 4]; 2] }
+    }
+}
+
+/// Decode slice_data() starting at `first_mb`
+pub fn decode_slice_data(
+    r: &mut BitReader,
+    pic: &mut PictureState,
+    ctx: &mut SliceContext,
+    first_mb: usize,
+) -> DecoderResult<()> {
+    let total = pic.mb_width * pic.mb_height;
+    let mut addr = first_mb;
+    loop {
+        if ctx.is_p {
+            let skip_run = r.read_ue()? as usize;
+            for _ in 0..skip_run {
+                if addr >= total {
+                    return Err(DecoderError::InvalidBitstream("mb_skip_run past end of picture".into()));
+                }
+                MbDecoder::new(pic, ctx, addr).decode_skip()?;
+                addr += 1;
+            }
+            if skip_run > 0 && !r.more_rbsp_data() {
+                return Ok(());
+            }
+        }
+        if addr >= total {
+            return Err(DecoderError::InvalidBitstream("Slice data past end of picture".into()));
+        }
+        MbDecoder::new(pic, ctx, addr).decode(r)?;
+        addr += 1;
+        if !r.more_rbsp_data() {
+            return Ok(());
+        }
+    }
+}
+
+struct MbDecoder<'p, 'c, 'r> {
+    pic: &'p mut PictureState,
+    ctx: &'c mut SliceContext<'r>,
+    addr: usize,
+    mb_x: usize,
+    mb_y: usize,
+    cur: MbInfo,
+    /// 4x4 blocks whose motion is already known
+    mask: u16,
+}
+
+impl<'p, 'c, 'r> MbDecoder<'p, 'c, 'r> {
+    fn new(pic: &'p mut PictureState, ctx: &'c mut SliceContext<'r>, addr: usize) -> Self {
+        let (mb_x, mb_y) = (addr % pic.mb_width, addr / pic.mb_width);
+        let cur = MbInfo { slice: Some(ctx.slice_num), ..MbInfo::default() };
+        Self { pic, ctx, addr, mb_x, mb_y, cur, mask: 0 }
+    }
+
+    /// Neighbouring macroblock if decoded in the current slice
+    fn neighbor(&self, dx: isize, dy: isize) -> Option<MbInfo> {
+        let x = self.mb_x as isize + dx;
+        let y = self.mb_y as isize + dy;
+        if x < 0 || y < 0 || x >= self.pic.mb_width as isize || y >= self.pic.mb_height as isize {
+            return None;
+        }
+        let info = self.pic.mbs[y as usize * self.pic.mb_width + x as usize];
+        (info.slice == Some(self.ctx.slice_num)).then_some(info)
+    }
+
+    /// Neighbour usable for intra prediction
+    fn intra_neighbor(&self, dx: isize, dy: isize) -> bool {
+        self.neighbor(dx, dy).is_some_and(|m| !self.ctx.constrained_intra_pred || m.kind.is_intra())
+    }
+
+    fn finish(mut self) {
+        self.cur.qpc = chroma_qp(self.cur.qp, self.ctx.chroma_qp_offset);
+        self.pic.mbs[self.addr] = self.cur;
+    }
+
+    fn decode(self, r: &mut BitReader) -> DecoderResult<()> {
+        let mb_type = r.read_ue()?;
+        let intra_type = if self.ctx.is_p {
+            if mb_type < 5 {
+                return self.decode_inter(r, mb_type);
+            }
+            mb_type - 5
+        } else {
+            mb_type
+        };
+        match intra_type {
+            0 => self.decode_intra(r, None),
+            1..=24 => self.decode_intra(r, Some(intra_type - 1)),
+            25 => self.decode_pcm(r),
+            _ => Err(DecoderError::InvalidBitstream(format!("Invalid mb_type {}", mb_type))),
+        }
+    }
+
+    fn decode_pcm(mut self, r: &mut BitReader) -> DecoderResult<()> {
+        r.byte_align();
+        let (x0, y0) = (self.mb_x * 16, self.mb_y * 16);
+        let stride = self.pic.frame.planes[0].stride;
+        for y in 0..16 {
+            for x in 0..16 {
+                self.pic.frame.planes[0].data[(y0 + y) * stride + x0 + x] = r.read_bits(8)? as u8;
+            }
+        }
+        for plane in 1..3 {
+            let stride = self.pic.frame.planes[plane].stride;
+            for y in 0..8 {
+                for x in 0..8 {
+                    self.pic.frame.planes[plane].data[(y0 / 2 + y) * stride + x0 / 2 + x] = r.read_bits(8)? as u8;
+                }
+            }
+        }
+        self.cur.kind = MbKind::IPcm;
+        self.cur.total_coeff = [16; 16];
+        self.cur.total_coeff_chroma = [[16; 4]; 2];
+        // Deblocking uses QP 0; the running QP is unchanged
+        self.cur.qp = 0;
+        self.finish();
+        Ok(())
+    }
+
+    /// `i16x16` carries (mb_type - 1) for Intra_16x16 macroblocks
+    fn decode_intra(mut self, r: &mut BitReader, i16x16: Option<u32>) -> DecoderResult<()> {
+        let mut modes = [2u8; 16];
+        if i16x16.is_none() {
+            self.cur.kind = MbKind::Intra4x4;
+            let left = self.neighbor(-1, 0).filter(|m| !self.ctx.constrained_intra_pred || m.kind.is_intra());
+            let top = self.neighbor(0, -1).filter(|m| !self.ctx.constrained_intra_pred || m.kind.is_intra());
+            for &raster in &BLOCK_RASTER {
+                let (bx, by) = (raster % 4, raster / 4);
+                let a = if bx > 0 { Some(modes[raster - 1]) } else { left.map(|m| m.intra_modes[raster + 3]) };
+                let b = if by > 0 { Some(modes[raster - 4]) } else { top.map(|m| m.intra_modes[raster + 12]) };
+                let predicted = match (a, b) {
+                    (Some(a), Some(b)) => a.min(b),
+                    _ => 2,
+                };
+                modes[raster] = if r.read_bit()? {
+                    predicted
+                } else {
+                    let rem = r.read_bits(3)? as u8;
+                    if rem < predicted { rem } else { rem + 1 }
+                };
+            }
+        } else {
+            self.cur.kind = MbKind::Intra16x16;
+        }
+        self.cur.intra_modes = modes;
+
+        let chroma_mode = r.read_ue()?;
+        if chroma_mode > 3 {
+            return Err(DecoderError::InvalidBitstream("Invalid intra_chroma_pred_mode".into()));
+        }
+        let cbp = match i16x16 {
+            Some(t) => (if t >= 12 { 15 } else { 0 }) | (((t / 4) % 3) << 4),
+            None => {
+                let code = r.read_ue()? as usize;
+                *INTRA_CBP.get(code).ok_or_else(|| DecoderError::InvalidBitstream("Invalid coded_block_pattern".into()))? as u32
+            }
+        };
+        let residual = self.read_residual(r, cbp, i16x16.is_some())?;
+
+        // Luma prediction and residual
+        let avail = |bx: bool, by: bool, d: &Self| IntraNeighbors {
+            left: bx || d.intra_neighbor(-1, 0),
+            top: by || d.intra_neighbor(0, -1),
+            top_right: false,
+            top_left: match (bx, by) {
+                (true, true) => true,
+                (false, true) => d.intra_neighbor(-1, 0),
+                (true, false) => d.intra_neighbor(0, -1),
+                (false, false) => d.intra_neighbor(-1, -1),
+            },
+        };
+        let (x0, y0) = (self.mb_x * 16, self.mb_y * 16);
+        let qp = self.ctx.qp;
+        let stride = self.pic.frame.planes[0].stride;
+        match i16x16 {
+            Some(t) => {
+                let n = avail(false, false, &self);
+                predict_intra16x16(&mut self.pic.frame.planes[0].data, stride, x0, y0, (t % 4) as u8, n);
+                let dc = luma_dc_dequant(&residual.luma_dc, qp);
+                for (raster, &dc) in dc.iter().enumerate() {
+                    let mut block = dequant_4x4(&residual.luma[raster], qp, true);
+                    block[0] = dc;
+                    if block.iter().any(|&c| c != 0) {
+                        idct_add_4x4(&block, &mut self.pic.frame.planes[0].data, stride, x0 + (raster % 4) * 4, y0 + (raster / 4) * 4);
+                    }
+                }
+            }
+            None => {
+                for &raster in &BLOCK_RASTER {
+                    let (bx, by) = (raster % 4, raster / 4);
+                    let mut n = avail(bx > 0, by > 0, &self);
+                    n.top_right = if by == 0 {
+                        if bx < 3 { self.intra_neighbor(0, -1) } else { self.intra_neighbor(1, -1) }
+                    } else {
+                        bx < 3 && BLOCK_RASTER[raster - 3] < BLOCK_RASTER[raster]
+                    };
+                    let (x, y) = (x0 + bx * 4, y0 + by * 4);
+                    predict_intra4x4(&mut self.pic.frame.planes[0].data, stride, x, y, modes[raster], n);
+                    if self.cur.total_coeff[raster] > 0 {
+                        let block = dequant_4x4(&residual.luma[raster], qp, false);
+                        idct_add_4x4(&block, &mut self.pic.frame.planes[0].data, stride, x, y);
+                    }
+                }
+            }
+        }
+
+        // Chroma
+        let n = avail(false, false, &self);
+        for plane in 1..3 {
+            let stride = self.pic.frame.planes[plane].stride;
+            predict_chroma(&mut self.pic.frame.planes[plane].data, stride, x0 / 2, y0 / 2, chroma_mode as u8, n);
+        }
+        self.add_chroma_residual(&residual);
+        self.cur.qp = qp;
+        self.finish();
+        Ok(())
+    }
+
+    fn decode_skip(mut self) -> DecoderResult<()> {
+        self.cur.kind = MbKind::Skip;
+        let a = self.motion_at(-1, 0);
+        let b = self.motion_at(0, -1);
+        let zero = Some((0i8, [0i16; 2]));
+        let part = Partition { x: 0, y: 0, w: 4, h: 4, ref_idx: 0, mvd: [0; 2] };
+        let mv = if a.is_none() || b.is_none() || a == zero || b == zero {
+            [0, 0]
+        } else {
+            self.predict_mv(&part)
+        };
+        self.set_motion(&part, mv)?;
+        self.motion_compensate(&part)?;
+        self.cur.qp = self.ctx.qp;
+        self.finish();
+        Ok(())
+    }
+
+    fn decode_inter(mut self, r: &mut BitReader, mb_type: u32) -> DecoderResult<()> {
+        self.cur.kind = MbKind::Inter;
+        let num_ref = self.ctx.num_ref_idx_active;
+        let read_ref = |r: &mut BitReader| -> DecoderResult<i8> {
+            if num_ref > 1 { Ok(read_te(r, num_ref - 1)? as i8) } else { Ok(0) }
+        };
+
+        let mut parts: Vec<Partition> = Vec::with_capacity(16);
+        let mb_part = |x, y, w, h| Partition { x, y, w, h, ref_idx: 0, mvd: [0; 2] };
+        match mb_type {
+            0 => parts.push(mb_part(0, 0, 4, 4)),
+            1 => parts.extend([mb_part(0, 0, 4, 2), mb_part(0, 2, 4, 2)]),
+            2 => parts.extend([mb_part(0, 0, 2, 4), mb_part(2, 0, 2, 4)]),
+            _ => {
+                let mut sub_types = [0u32; 4];
+                for t in &mut sub_types {
+                    *t = r.read_ue()?;
+                    if *t > 3 {
+                        return Err(DecoderError::InvalidBitstream("Invalid sub_mb_type".into()));
+                    }
+                }
+                let mut refs = [0i8; 4];
+                if mb_type == 3 {
+                    for rf in &mut refs {
+                        *rf = read_ref(r)?;
+                    }
+                }
+                for (i, &t) in sub_types.iter().enumerate() {
+                    let (x, y) = ((i % 2) * 2, (i / 2) * 2);
+                    let subs: &[(usize, usize, usize, usize)] = match t {
+                        0 => &[(0, 0, 2, 2)],
+                        1 => &[(0, 0, 2, 1), (0, 1, 2, 1)],
+                        2 => &[(0, 0, 1, 2), (1, 0, 1, 2)],
+                        _ => &[(0, 0, 1, 1), (1, 0, 1, 1), (0, 1, 1, 1), (1, 1, 1, 1)],
+                    };
+                    for &(sx, sy, w, h) in subs {
+                        parts.push(Partition { x: x + sx, y: y + sy, w, h, ref_idx: refs[i], mvd: [0; 2] });
+                    }
+                }
+            }
+        }
+        if mb_type < 3 {
+            for part in &mut parts {
+                part.ref_idx = read_ref(r)?;
+            }
+        }
+        for part in &mut parts {
+            part.mvd = [r.read_se()?, r.read_se()?];
+        }
+
+        for part in &parts {
+            let mvp = self.predict_mv(part);
+            let mv = [
+                (mvp[0] as i32 + part.mvd[0]) as i16,
+                (mvp[1] as i32 + part.mvd[1]) as i16,
+            ];
+            self.set_motion(part, mv)?;
+        }
+        for part in &parts {
+            self.motion_compensate(part)?;
+        }
+
+        let code = r.read_ue()? as usize;
+        let cbp = *INTER_CBP.get(code).ok_or_else(|| DecoderError::InvalidBitstream("Invalid coded_block_pattern".into()))? as u32;
+        let residual = self.read_residual(r, cbp, false)?;
+        let qp = self.ctx.qp;
+        let (x0, y0) = (self.mb_x * 16, self.mb_y * 16);
+        let stride = self.pic.frame.planes[0].stride;
+        for raster in 0..16 {
+            if self.cur.total_coeff[raster] > 0 {
+                let block = dequant_4x4(&residual.luma[raster], qp, false);
+                idct_add_4x4(&block, &mut self.pic.frame.planes[0].data, stride, x0 + (raster % 4) * 4, y0 + (raster / 4) * 4);
+            }
+        }
+        self.add_chroma_residual(&residual);
+        self.cur.qp = qp;
+        self.finish();
+        Ok(())
+    }
+
+    /// Parse mb_qp_delta and residual() for the given coded_block_pattern
+    fn read_residual(&mut self, r: &mut BitReader, cbp: u32, i16x16: bool) -> DecoderResult<Residual> {
+        let mut res = Residual::new();
+        let (cbp_luma, cbp_chroma) = (cbp & 15, cbp >> 4);
+        if cbp > 0 || i16x16 {
+            let delta = r.read_se()?;
+            if !(-26..=25).contains(&delta) {
+                return Err(DecoderError::InvalidBitstream("Invalid mb_qp_delta".into()));
+            }
+            self.ctx.qp = (self.ctx.qp + delta + 52) % 52;
+        }
+
+        if i16x16 {
+            let nc = self.luma_nc(0);
+            read_residual_block(r, nc, 16, &mut res.luma_dc, 0)?;
+        }
+        for (blk, &raster) in BLOCK_RASTER.iter().enumerate() {
+            if cbp_luma & (1 << (blk / 4)) == 0 {
+                continue;
+            }
+            let nc = self.luma_nc(raster);
+            let n = if i16x16 {
+                read_residual_block(r, nc, 15, &mut res.luma[raster], 1)?
+            } else {
+                read_residual_block(r, nc, 16, &mut res.luma[raster], 0)?
+            };
+            self.cur.total_coeff[raster] = n as u8;
+        }
+        if cbp_chroma > 0 {
+            for c in 0..2 {
+                read_residual_block(r, -1, 4, &mut res.chroma_dc[c], 0)?;
+            }
+        }
+        if cbp_chroma > 1 {
+            for c in 0..2 {
+                for b in 0..4 {
+                    let nc = self.chroma_nc(c, b);
+                    let n = read_residual_block(r, nc, 15, &mut res.chroma_ac[c][b], 1)?;
+                    self.cur.total_coeff_chroma[c][b] = n as u8;
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    fn add_chroma_residual(&mut self, res: &Residual) {
+        let qpc = chroma_qp(self.ctx.qp, self.ctx.chroma_qp_offset);
+        let (x0, y0) = (self.mb_x * 8, self.mb_y * 8);
+        for c in 0..2 {
+            let dc = chroma_dc_dequant(&res.chroma_dc[c], qpc);
+            let plane = &mut self.pic.frame.planes[c + 1];
+            for (b, &dc) in dc.iter().enumerate() {
+                let mut block = dequant_4x4(&res.chroma_ac[c][b], qpc, true);
+                block[0] = dc;
+                if block.iter().any(|&v| v != 0) {
+                    idct_add_4x4(&block, &mut plane.data, plane.stride, x0 + (b % 2) * 4, y0 + (b / 2) * 4);
+                }
+            }
+        }
+    }
+
+    /// nC for a luma block (clause 9.2.1)
+    fn luma_nc(&self, raster: usize) -> i32 {
+        let (bx, by) = (raster % 4, raster / 4);
+        let a = if bx > 0 {
+            Some(self.cur.total_coeff[raster - 1])
+        } else {
+            self.neighbor(-1, 0).map(|m| m.total_coeff[raster + 3])
+        };
+        let b = if by > 0 {
+            Some(self.cur.total_coeff[raster - 4])
+        } else {
+            self.neighbor(0, -1).map(|m| m.total_coeff[raster + 12])
+        };
+        combine_nc(a, b)
+    }
+
+    fn chroma_nc(&self, c: usize, blk: usize) -> i32 {
+        let (bx, by) = (blk % 2, blk / 2);
+        let a = if bx > 0 {
+            Some(self.cur.total_coeff_chroma[c][blk - 1])
+        } else {
+            self.neighbor(-1, 0).map(|m| m.total_coeff_chroma[c][blk + 1])
+        };
+        let b = if by > 0 {
+            Some(self.cur.total_coeff_chroma[c][blk - 2])
+        } else {
+            self.neighbor(0, -1).map(|m| m.total_coeff_chroma[c][blk + 2])
+        };
+        combine_nc(a, b)
+    }
+
+    /// Motion of the 4x4 block at (x, y) relative to this macroblock, x and y in -1..=4
+    fn motion_at(&self, x: i32, y: i32) -> Option<Motion> {
+        if (0..4).contains(&x) && (0..4).contains(&y) {
+            let i = (x + 4 * y) as usize;
+            return (self.mask & (1 << i) != 0)
+                .then(|| (self.cur.ref_idx[(x / 2 + 2 * (y / 2)) as usize], self.cur.mv[i]));
+        }
+        if x >= 4 && y >= 0 {
+            return None;
+        }
+        let dx = if x < 0 { -1 } else if x >= 4 { 1 } else { 0 };
+        let dy = if y < 0 { -1 } else { 0 };
+        let m = self.neighbor(dx, dy)?;
+        if m.kind.is_intra() {
+            return Some((-1, [0, 0]));
+        }
+        let (bx, by) = (x.rem_euclid(4) as usize, y.rem_euclid(4) as usize);
+        Some((m.ref_idx[bx / 2 + 2 * (by / 2)], m.mv[bx + 4 * by]))
+    }
+
+    /// Motion vector predictor (clause 8.4.1.3)
+    fn predict_mv(&self, part: &Partition) -> [i16; 2] {
+        let (x, y, w) = (part.x as i32, part.y as i32, part.w as i32);
+        let a = self.motion_at(x - 1, y);
+        let mut b = self.motion_at(x, y - 1);
+        let mut c = self.motion_at(x + w, y - 1).or_else(|| self.motion_at(x - 1, y - 1));
+        if b.is_none() && c.is_none() && a.is_some() {
+            b = a;
+            c = a;
+        }
+        let unavailable = (-1i8, [0i16; 2]);
+        let (a, b, c) = (a.unwrap_or(unavailable), b.unwrap_or(unavailable), c.unwrap_or(unavailable));
+        let ref_idx = part.ref_idx;
+
+        // Directional prediction for 16x8 and 8x16 partitions
+        if part.w == 4 && part.h == 2 {
+            if part.y == 0 && b.0 == ref_idx {
+                return b.1;
+            }
+            if part.y == 2 && a.0 == ref_idx {
+                return a.1;
+            }
+        } else if part.w == 2 && part.h == 4 {
+            if part.x == 0 && a.0 == ref_idx {
+                return a.1;
+            }
+            if part.x == 2 && c.0 == ref_idx {
+                return c.1;
+            }
+        }
+
+        let matching: Vec<&Motion> = [&a, &b, &c].into_iter().filter(|m| m.0 == ref_idx).collect();
+        if matching.len() == 1 {
+            return matching[0].1;
+        }
+        let median = |p: i16, q: i16, r: i16| p.max(q).min(p.min(q).max(r));
+        [median(a.1[0], b.1[0], c.1[0]), median(a.1[1], b.1[1], c.1[1])]
+    }
+
+    fn set_motion(&mut self, part: &Partition, mv: [i16; 2]) -> DecoderResult<()> {
+        let poc = self
+            .ctx
+            .refs
+            .get(part.ref_idx as usize)
+            .map(|r| r.poc)
+            .ok_or_else(|| DecoderError::InvalidBitstream("Reference index out of range".into()))?;
+        for y in part.y..part.y + part.h {
+            for x in part.x..part.x + part.w {
+                self.cur.mv[x + 4 * y] = mv;
+                self.mask |= 1 << (x + 4 * y);
+                self.cur.ref_idx[x / 2 + 2 * (y / 2)] = part.ref_idx;
+                self.cur.ref_poc[x / 2 + 2 * (y / 2)] = poc;
+            }
+        }
+        Ok(())
+    }
+
+    fn motion_compensate(&mut self, part: &Partition) -> DecoderResult<()> {
+        let reference = self
+            .ctx
+            .refs
+            .get(part.ref_idx as usize)
+            .ok_or_else(|| DecoderError::InvalidBitstream("Reference index out of range".into()))?
+            .frame;
+        let mv = self.cur.mv[part.x + 4 * part.y];
+        let (w, h) = (self.pic.frame.width as usize, self.pic.frame.height as usize);
+        for plane in 0..3 {
+            let src = &reference.planes[plane];
+            let dst = &mut self.pic.frame.planes[plane];
+            let (pw, ph) = if plane == 0 { (w, h) } else { (w / 2, h / 2) };
+            let view = RefPlane { data: &src.data, stride: src.stride, width: pw, height: ph };
+            if plane == 0 {
+                let (x, y) = (self.mb_x * 16 + part.x * 4, self.mb_y * 16 + part.y * 4);
+                predict_luma(view, &mut dst.data, dst.stride, x, y, part.w * 4, part.h * 4, mv);
+            } else {
+                let (x, y) = (self.mb_x * 8 + part.x * 2, self.mb_y * 8 + part.y * 2);
+                predict_chroma_mc(view, &mut dst.data, dst.stride, x, y, part.w * 2, part.h * 2, mv);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn combine_nc(a: Option<u8>, b: Option<u8>) -> i32 {
+    match (a, b) {
+        (Some(a), Some(b)) => (a as i32 + b as i32 + 1) >> 1,
+        (Some(a), None) => a as i32,
+        (None, Some(b)) => b as i32,
+        (None, None) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_order_is_involution() {
+        for i in 0..16 {
+            assert_eq!(BLOCK_RASTER[BLOCK_RASTER[i]], i);
+        }
+    }
+
+    #[test]
+    fn test_nc_combination() {
+        assert_eq!(combine_nc(Some(3), Some(4)), 4);
+        assert_eq!(combine_nc(None, Some(4)), 4);
+        assert_eq!(combine_nc(None, None), 0);
+    }
+}
@@ -0,0 +1,969 @@
+//! H.264/AVC Decoder
+//!
+//! Pure-Rust implementation of H.264 video decoding.
+//!
+//! Supports the constrained baseline feature set: CAVLC I and P slices,
+//! 4:2:0 8-bit frames, multiple reference frames with list modification and
+//! adaptive marking, and the in-loop deblocking filter. Input may be Annex B
+//! byte streams or length-prefixed NAL units (MP4 `avcC`).
+
+pub mod cavlc;
+pub mod deblock;
+pub mod macroblock;
+pub mod recon;
+
+use super::{BitReader, DecodedPictureBuffer, ReferenceFrame, VideoDecoderState};
+use crate::decoders::{
+    VideoFrame, EncodedPacket, DecoderCaps, DecoderResult, DecoderError,
+    VideoDecoderTrait, PixelFormat, Plane,
+};
+use deblock::{deblock_picture, DeblockParams};
+use macroblock::{decode_slice_data, PictureState, RefPic, SliceContext};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// H.264 NAL unit types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum NalUnitType {
+    Unspecified = 0,
+    NonIdrSlice = 1,
+    IdrSlice = 5,
+    Sei = 6,
+    Sps = 7,
+    Pps = 8,
+    AccessUnitDelimiter = 9,
+}
+
+impl TryFrom<u8> for NalUnitType {
+    type Error = DecoderError;
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            0 => Ok(Self::Unspecified), 1 => Ok(Self::NonIdrSlice),
+            5 => Ok(Self::IdrSlice), 6 => Ok(Self::Sei),
+            7 => Ok(Self::Sps), 8 => Ok(Self::Pps),
+            9 => Ok(Self::AccessUnitDelimiter),
+            _ => Ok(Self::Unspecified),
+        }
+    }
+}
+
+/// Sequence Parameter Set
+#[derive(Debug, Clone)]
+pub struct Sps {
+    pub sps_id: u8,
+    pub profile_idc: u8,
+    pub level_idc: u8,
+    pub chroma_format_idc: u8,
+    pub bit_depth: u8,
+    pub scaling_matrix_present: bool,
+    pub log2_max_frame_num: u8,
+    pub pic_order_cnt_type: u8,
+    pub log2_max_pic_order_cnt_lsb: u8,
+    pub delta_pic_order_always_zero: bool,
+    pub offset_for_non_ref_pic: i32,
+    pub offset_for_top_to_bottom_field: i32,
+    pub offset_for_ref_frame: Vec<i32>,
+    pub max_num_ref_frames: u8,
+    pub pic_width_in_mbs: u32,
+    pub pic_height_in_map_units: u32,
+    pub frame_mbs_only_flag: bool,
+    /// Frame cropping offsets (left, right, top, bottom) in crop units
+    pub crop: [u32; 4],
+}
+
+impl Sps {
+    pub fn max_frame_num(&self) -> u32 { 1 << self.log2_max_frame_num }
+}
+
+/// Picture Parameter Set
+#[derive(Debug, Clone)]
+pub struct Pps {
+    pub pps_id: u8,
+    pub sps_id: u8,
+    pub entropy_coding_mode_flag: bool,
+    pub bottom_field_pic_order_in_frame_present: bool,
+    pub num_ref_idx_l0_default_active: u8,
+    pub num_ref_idx_l1_default_active: u8,
+    pub weighted_pred_flag: bool,
+    pub weighted_bipred_idc: u8,
+    pub pic_init_qp: i8,
+    pub chroma_qp_index_offset: i8,
+    pub deblocking_filter_control_present: bool,
+    pub constrained_intra_pred: bool,
+    pub redundant_pic_cnt_present: bool,
+    pub transform_8x8_mode: bool,
+}
+
+/// Slice type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceType { P = 0, B = 1, I = 2, Sp = 3, Si = 4 }
+
+impl TryFrom<u32> for SliceType {
+    type Error = DecoderError;
+    fn try_from(v: u32) -> Result<Self, Self::Error> {
+        match v % 5 {
+            0 => Ok(Self::P), 1 => Ok(Self::B), 2 => Ok(Self::I),
+            3 => Ok(Self::Sp), _ => Ok(Self::Si),
+        }
+    }
+}
+
+/// Memory management control operation (adaptive reference marking)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mmco {
+    /// Mark a short-term picture unused (difference_of_pic_nums_minus1)
+    UnmarkShortTerm(u32),
+    /// Mark a long-term picture unused (long_term_pic_num)
+    UnmarkLongTerm(u32),
+    /// Convert a short-term picture to long-term
+    ShortToLong { difference_of_pic_nums_minus1: u32, long_term_frame_idx: u32 },
+    /// Set max_long_term_frame_idx_plus1
+    MaxLongTermIdx(u32),
+    /// Mark all references unused
+    UnmarkAll,
+    /// Mark the current picture long-term
+    CurrentToLong(u32),
+}
+
+/// Slice header fields needed for frame decoding
+#[derive(Debug, Clone)]
+pub struct SliceHeader {
+    pub nal_ref_idc: u8,
+    pub idr: bool,
+    pub first_mb: u32,
+    pub slice_type: SliceType,
+    pub pps_id: u8,
+    pub frame_num: u32,
+    pub idr_pic_id: u32,
+    pub pic_order_cnt_lsb: u32,
+    pub delta_pic_order_cnt_bottom: i32,
+    pub delta_pic_order_cnt: [i32; 2],
+    pub num_ref_idx_active: u32,
+    /// ref_pic_list_modification entries (modification_of_pic_nums_idc, value)
+    pub ref_list_modifications: Vec<(u32, u32)>,
+    pub no_output_of_prior_pics: bool,
+    pub long_term_reference: bool,
+    pub mmcos: Vec<Mmco>,
+    pub slice_qp_delta: i32,
+    pub deblock: DeblockParams,
+}
+
+/// Picture under construction
+#[derive(Debug)]
+struct CurrentPicture {
+    state: PictureState,
+    header: SliceHeader,
+    poc: i32,
+    poc_msb: i32,
+    frame_num_offset: i32,
+    pts: Duration,
+    dts: Duration,
+    /// Deblocking controls per slice number
+    slices: Vec<DeblockParams>,
+}
+
+/// H.264 Decoder
+#[derive(Debug)]
+pub struct H264Decoder {
+    sps_map: HashMap<u8, Sps>,
+    pps_map: HashMap<u8, Pps>,
+    /// Reference pictures. For long-term entries `frame_num` holds LongTermFrameIdx.
+    dpb: DecodedPictureBuffer,
+    state: VideoDecoderState,
+    /// Decoded frames waiting for output, with their POC
+    pending_output: Vec<(i32, VideoFrame)>,
+    active_sps_id: Option<u8>,
+    /// NAL length prefix size for `avcC` streams (None = Annex B)
+    nal_length_size: Option<usize>,
+    picture: Option<CurrentPicture>,
+    prev_poc_msb: i32,
+    prev_poc_lsb: i32,
+    prev_frame_num: u32,
+    prev_frame_num_offset: i32,
+    max_long_term_frame_idx: Option<u32>,
+}
+
+impl H264Decoder {
+    pub fn new() -> Self {
+        Self {
+            sps_map: HashMap::new(),
+            pps_map: HashMap::new(),
+            dpb: DecodedPictureBuffer::new(16),
+            state: VideoDecoderState::default(),
+            pending_output: Vec::new(),
+            active_sps_id: None,
+            nal_length_size: None,
+            picture: None,
+            prev_poc_msb: 0,
+            prev_poc_lsb: 0,
+            prev_frame_num: 0,
+            prev_frame_num_offset: 0,
+            max_long_term_frame_idx: None,
+        }
+    }
+
+    /// Split an Annex B byte stream into NAL units
+    fn parse_nal_units(data: &[u8]) -> Vec<&[u8]> {
+        let mut starts = Vec::new();
+        let mut i = 0;
+        while i + 3 <= data.len() {
+            if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+                starts.push(i + 3);
+                i += 3;
+            } else {
+                i += 1;
+            }
+        }
+        let mut units = Vec::with_capacity(starts.len());
+        for (k, &start) in starts.iter().enumerate() {
+            let mut end = starts.get(k + 1).map(|&next| next - 3).unwrap_or(data.len());
+            // Drop the leading zero of a 4-byte start code and trailing_zero_8bits
+            while end > start && data[end - 1] == 0 {
+                end -= 1;
+            }
+            if start < end {
+                units.push(&data[start..end]);
+            }
+        }
+        units
+    }
+
+    /// Split length-prefixed NAL units (MP4 sample format)
+    fn parse_length_prefixed(data: &[u8], length_size: usize) -> Vec<&[u8]> {
+        let mut units = Vec::new();
+        let mut pos = 0;
+        while pos + length_size <= data.len() {
+            let len = data[pos..pos + length_size].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+            pos += length_size;
+            if len == 0 || pos + len > data.len() {
+                break;
+            }
+            units.push(&data[pos..pos + len]);
+            pos += len;
+        }
+        units
+    }
+
+    /// Remove emulation prevention bytes (NAL -> RBSP)
+    fn nal_to_rbsp(nal: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(nal.len());
+        let mut zeros = 0;
+        for &b in nal {
+            if zeros >= 2 && b == 3 {
+                zeros = 0;
+                continue;
+            }
+            zeros = if b == 0 { zeros + 1 } else { 0 };
+            out.push(b);
+        }
+        out
+    }
+
+    fn skip_scaling_list(r: &mut BitReader, size: usize) -> DecoderResult<()> {
+        let (mut last, mut next) = (8i32, 8i32);
+        for _ in 0..size {
+            if next != 0 {
+                next = (last + r.read_se()? + 256) % 256;
+            }
+            if next != 0 {
+                last = next;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_sps(&mut self, data: &[u8]) -> DecoderResult<()> {
+        let mut r = BitReader::new(&data[1..]);
+        let profile_idc = r.read_bits(8)? as u8;
+        r.skip(8)?; // constraint flags
+        let level_idc = r.read_bits(8)? as u8;
+        let sps_id = r.read_ue()? as u8;
+        let mut chroma_format_idc = 1;
+        let mut bit_depth = 8;
+        let mut scaling_matrix_present = false;
+        if matches!(profile_idc, 100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135) {
+            chroma_format_idc = r.read_ue()? as u8;
+            if chroma_format_idc == 3 { r.skip(1)?; }
+            bit_depth = r.read_ue()? as u8 + 8;
+            r.read_ue()?; // bit_depth_chroma_minus8
+            r.skip(1)?; // qpprime_y_zero_transform_bypass_flag
+            scaling_matrix_present = r.read_bit()?;
+            if scaling_matrix_present {
+                let lists = if chroma_format_idc == 3 { 12 } else { 8 };
+                for i in 0..lists {
+                    if r.read_bit()? {
+                        Self::skip_scaling_list(&mut r, if i < 6 { 16 } else { 64 })?;
+                    }
+                }
+            }
+        }
+        let log2_max_frame_num = r.read_ue()? as u8 + 4;
+        let pic_order_cnt_type = r.read_ue()? as u8;
+        let mut log2_max_pic_order_cnt_lsb = 4;
+        let mut delta_pic_order_always_zero = false;
+        let mut offset_for_non_ref_pic = 0;
+        let mut offset_for_top_to_bottom_field = 0;
+        let mut offset_for_ref_frame = Vec::new();
+        match pic_order_cnt_type {
+            0 => log2_max_pic_order_cnt_lsb = r.read_ue()? as u8 + 4,
+            1 => {
+                delta_pic_order_always_zero = r.read_bit()?;
+                offset_for_non_ref_pic = r.read_se()?;
+                offset_for_top_to_bottom_field = r.read_se()?;
+                let n = r.read_ue()?;
+                if n > 255 { return Err(DecoderError::InvalidBitstream("Too many ref frames in POC cycle".into())); }
+                for _ in 0..n { offset_for_ref_frame.push(r.read_se()?); }
+            }
+            _ => {}
+        }
+        let max_num_ref_frames = r.read_ue()? as u8;
+        r.skip(1)?; // gaps_in_frame_num_value_allowed_flag
+        let w = r.read_ue()? + 1;
+        let h = r.read_ue()? + 1;
+        let frame_mbs_only = r.read_bit()?;
+        if !frame_mbs_only { r.skip(1)?; } // mb_adaptive_frame_field_flag
+        r.skip(1)?; // direct_8x8_inference_flag
+        let mut crop = [0; 4];
+        if r.read_bit()? {
+            for c in &mut crop { *c = r.read_ue()?; }
+        }
+        self.sps_map.insert(sps_id, Sps {
+            sps_id, profile_idc, level_idc, chroma_format_idc, bit_depth, scaling_matrix_present,
+            log2_max_frame_num, pic_order_cnt_type, log2_max_pic_order_cnt_lsb,
+            delta_pic_order_always_zero, offset_for_non_ref_pic, offset_for_top_to_bottom_field,
+            offset_for_ref_frame, max_num_ref_frames, pic_width_in_mbs: w, pic_height_in_map_units: h,
+            frame_mbs_only_flag: frame_mbs_only, crop,
+        });
+        Ok(())
+    }
+
+    fn parse_pps(&mut self, data: &[u8]) -> DecoderResult<()> {
+        let mut r = BitReader::new(&data[1..]);
+        let pps_id = r.read_ue()? as u8;
+        let sps_id = r.read_ue()? as u8;
+        let entropy = r.read_bit()?;
+        let bottom_field_pic_order = r.read_bit()?;
+        let nsg = r.read_ue()? + 1;
+        if nsg > 1 { return Err(DecoderError::Unsupported("slice groups".into())); }
+        let l0 = r.read_ue()? as u8 + 1;
+        let l1 = r.read_ue()? as u8 + 1;
+        let weighted_pred = r.read_bit()?;
+        let weighted_bipred = r.read_bits(2)? as u8;
+        let qp = r.read_se()? as i8 + 26;
+        r.read_se()?; // pic_init_qs_minus26
+        let chroma_qp_offset = r.read_se()? as i8;
+        let dbf = r.read_bit()?;
+        let constrained_intra_pred = r.read_bit()?;
+        let redundant_pic_cnt_present = r.read_bit()?;
+        let transform_8x8_mode = r.more_rbsp_data() && r.read_bit()?;
+        self.pps_map.insert(pps_id, Pps {
+            pps_id, sps_id, entropy_coding_mode_flag: entropy,
+            bottom_field_pic_order_in_frame_present: bottom_field_pic_order,
+            num_ref_idx_l0_default_active: l0, num_ref_idx_l1_default_active: l1,
+            weighted_pred_flag: weighted_pred, weighted_bipred_idc: weighted_bipred,
+            pic_init_qp: qp, chroma_qp_index_offset: chroma_qp_offset,
+            deblocking_filter_control_present: dbf, constrained_intra_pred,
+            redundant_pic_cnt_present, transform_8x8_mode,
+        });
+        Ok(())
+    }
+
+    fn parse_slice_header(&self, r: &mut BitReader, nal_ref_idc: u8, idr: bool) -> DecoderResult<SliceHeader> {
+        let first_mb = r.read_ue()?;
+        let slice_type = SliceType::try_from(r.read_ue()?)?;
+        let pps_id = r.read_ue()? as u8;
+        let pps = self.pps_map.get(&pps_id).ok_or_else(|| DecoderError::InvalidBitstream("Unknown PPS".into()))?;
+        let sps = self.sps_map.get(&pps.sps_id).ok_or_else(|| DecoderError::InvalidBitstream("No SPS".into()))?;
+
+        match slice_type {
+            SliceType::I | SliceType::P => {}
+            SliceType::B => return Err(DecoderError::Unsupported("B slices".into())),
+            _ => return Err(DecoderError::Unsupported("SP/SI slices".into())),
+        }
+        if pps.entropy_coding_mode_flag { return Err(DecoderError::Unsupported("CABAC entropy coding".into())); }
+        if !sps.frame_mbs_only_flag { return Err(DecoderError::Unsupported("interlaced coding".into())); }
+        if sps.chroma_format_idc != 1 || sps.bit_depth != 8 {
+            return Err(DecoderError::Unsupported("non 4:2:0 8-bit video".into()));
+        }
+        if sps.scaling_matrix_present || pps.transform_8x8_mode {
+            return Err(DecoderError::Unsupported("High profile tools".into()));
+        }
+        if pps.weighted_pred_flag && slice_type == SliceType::P {
+            return Err(DecoderError::Unsupported("weighted prediction".into()));
+        }
+
+        let frame_num = r.read_bits(sps.log2_max_frame_num)?;
+        let idr_pic_id = if idr { r.read_ue()? } else { 0 };
+        let mut pic_order_cnt_lsb = 0;
+        let mut delta_pic_order_cnt_bottom = 0;
+        let mut delta_pic_order_cnt = [0; 2];
+        if sps.pic_order_cnt_type == 0 {
+            pic_order_cnt_lsb = r.read_bits(sps.log2_max_pic_order_cnt_lsb)?;
+            if pps.bottom_field_pic_order_in_frame_present { delta_pic_order_cnt_bottom = r.read_se()?; }
+        }
+        if sps.pic_order_cnt_type == 1 && !sps.delta_pic_order_always_zero {
+            delta_pic_order_cnt[0] = r.read_se()?;
+            if pps.bottom_field_pic_order_in_frame_present { delta_pic_order_cnt[1] = r.read_se()?; }
+        }
+        if pps.redundant_pic_cnt_present { r.read_ue()?; }
+
+        let mut num_ref_idx_active = pps.num_ref_idx_l0_default_active as u32;
+        let mut ref_list_modifications = Vec::new();
+        if slice_type == SliceType::P {
+            if r.read_bit()? { num_ref_idx_active = r.read_ue()? + 1; }
+            if num_ref_idx_active > 32 { return Err(DecoderError::InvalidBitstream("num_ref_idx_active out of range".into())); }
+            if r.read_bit()? {
+                loop {
+                    let idc = r.read_ue()?;
+                    if idc == 3 { break; }
+                    if idc > 2 || ref_list_modifications.len() > 32 {
+                        return Err(DecoderError::InvalidBitstream("Invalid ref_pic_list_modification".into()));
+                    }
+                    ref_list_modifications.push((idc, r.read_ue()?));
+                }
+            }
+        }
+
+        let mut no_output_of_prior_pics = false;
+        let mut long_term_reference = false;
+        let mut mmcos = Vec::new();
+        if nal_ref_idc != 0 {
+            if idr {
+                no_output_of_prior_pics = r.read_bit()?;
+                long_term_reference = r.read_bit()?;
+            } else if r.read_bit()? {
+                loop {
+                    let op = match r.read_ue()? {
+                        0 => break,
+                        1 => Mmco::UnmarkShortTerm(r.read_ue()?),
+                        2 => Mmco::UnmarkLongTerm(r.read_ue()?),
+                        3 => Mmco::ShortToLong { difference_of_pic_nums_minus1: r.read_ue()?, long_term_frame_idx: r.read_ue()? },
+                        4 => Mmco::MaxLongTermIdx(r.read_ue()?),
+                        5 => Mmco::UnmarkAll,
+                        6 => Mmco::CurrentToLong(r.read_ue()?),
+                        _ => return Err(DecoderError::InvalidBitstream("Invalid MMCO".into())),
+                    };
+                    mmcos.push(op);
+                    if mmcos.len() > 66 { return Err(DecoderError::InvalidBitstream("Too many MMCOs".into())); }
+                }
+            }
+        }
+
+        let slice_qp_delta = r.read_se()?;
+        let mut deblock = DeblockParams::default();
+        if pps.deblocking_filter_control_present {
+            deblock.disable = r.read_ue()?;
+            if deblock.disable != 1 {
+                deblock.alpha_offset = r.read_se()? * 2;
+                deblock.beta_offset = r.read_se()? * 2;
+            }
+        }
+
+        Ok(SliceHeader {
+            nal_ref_idc, idr, first_mb, slice_type, pps_id, frame_num, idr_pic_id,
+            pic_order_cnt_lsb, delta_pic_order_cnt_bottom, delta_pic_order_cnt, num_ref_idx_active,
+            ref_list_modifications, no_output_of_prior_pics, long_term_reference, mmcos, slice_qp_delta, deblock,
+        })
+    }
+
+    /// Picture order count (clause 8.2.1), returning (POC, PicOrderCntMsb, FrameNumOffset)
+    fn compute_poc(&self, sps: &Sps, h: &SliceHeader) -> (i32, i32, i32) {
+        let (prev_msb, prev_lsb) = if h.idr { (0, 0) } else { (self.prev_poc_msb, self.prev_poc_lsb) };
+        let frame_num_offset = if h.idr {
+            0
+        } else if self.prev_frame_num > h.frame_num {
+            self.prev_frame_num_offset + sps.max_frame_num() as i32
+        } else {
+            self.prev_frame_num_offset
+        };
+        match sps.pic_order_cnt_type {
+            0 => {
+                let max_lsb = 1i32 << sps.log2_max_pic_order_cnt_lsb;
+                let lsb = h.pic_order_cnt_lsb as i32;
+                let msb = if lsb < prev_lsb && prev_lsb - lsb >= max_lsb / 2 {
+                    prev_msb + max_lsb
+                } else if lsb > prev_lsb && lsb - prev_lsb > max_lsb / 2 {
+                    prev_msb - max_lsb
+                } else {
+                    prev_msb
+                };
+                let top = msb + lsb;
+                (top.min(top + h.delta_pic_order_cnt_bottom), msb, frame_num_offset)
+            }
+            1 => {
+                let cycle = sps.offset_for_ref_frame.len() as i32;
+                let mut abs_frame_num = if cycle != 0 { frame_num_offset + h.frame_num as i32 } else { 0 };
+                if h.nal_ref_idc == 0 && abs_frame_num > 0 {
+                    abs_frame_num -= 1;
+                }
+                let mut expected = 0;
+                if abs_frame_num > 0 {
+                    let per_cycle: i32 = sps.offset_for_ref_frame.iter().sum();
+                    let cycle_cnt = (abs_frame_num - 1) / cycle;
+                    let in_cycle = ((abs_frame_num - 1) % cycle) as usize;
+                    expected = cycle_cnt * per_cycle + sps.offset_for_ref_frame[..=in_cycle].iter().sum::<i32>();
+                }
+                if h.nal_ref_idc == 0 {
+                    expected += sps.offset_for_non_ref_pic;
+                }
+                let top = expected + h.delta_pic_order_cnt[0];
+                let bottom = top + sps.offset_for_top_to_bottom_field + h.delta_pic_order_cnt[1];
+                (top.min(bottom), 0, frame_num_offset)
+            }
+            _ => {
+                let poc = if h.idr {
+                    0
+                } else if h.nal_ref_idc == 0 {
+                    2 * (frame_num_offset + h.frame_num as i32) - 1
+                } else {
+                    2 * (frame_num_offset + h.frame_num as i32)
+                };
+                (poc, 0, frame_num_offset)
+            }
+        }
+    }
+
+    /// PicNum of a short-term reference relative to the current frame_num
+    fn pic_num(frame_num: u32, current: u32, max_frame_num: u32) -> i32 {
+        if frame_num > current { frame_num as i32 - max_frame_num as i32 } else { frame_num as i32 }
+    }
+
+    fn start_picture(&mut self, header: &SliceHeader, pts: Duration, dts: Duration, out: &mut Vec<VideoFrame>) -> DecoderResult<()> {
+        let pps = &self.pps_map[&header.pps_id];
+        let sps = self.sps_map[&pps.sps_id].clone();
+        let (w, h) = (sps.pic_width_in_mbs * 16, sps.pic_height_in_map_units * 16);
+
+        if self.active_sps_id != Some(sps.sps_id) || self.state.width != w || self.state.height != h {
+            if !header.idr {
+                return Err(DecoderError::InvalidBitstream("Stream does not start with an IDR picture".into()));
+            }
+            self.drain_output(out);
+            self.dpb = DecodedPictureBuffer::new(sps.max_num_ref_frames.max(1) as usize);
+            self.active_sps_id = Some(sps.sps_id);
+            self.state.width = w;
+            self.state.height = h;
+        }
+
+        if header.idr {
+            self.dpb.clear();
+            self.max_long_term_frame_idx = None;
+            if header.no_output_of_prior_pics {
+                self.pending_output.clear();
+            } else {
+                self.drain_output(out);
+            }
+        }
+
+        let (poc, poc_msb, frame_num_offset) = self.compute_poc(&sps, header);
+
+        // Conceal macroblocks missing from the bitstream with the last reference
+        let y_size = (w * h) as usize;
+        let planes = match self.dpb.iter().last() {
+            Some(r) if !header.idr && r.frame.width == w && r.frame.height == h => r.frame.planes.clone(),
+            _ => vec![
+                Plane { data: vec![128; y_size], stride: w as usize },
+                Plane { data: vec![128; y_size / 4], stride: (w / 2) as usize },
+                Plane { data: vec![128; y_size / 4], stride: (w / 2) as usize },
+            ],
+        };
+        let frame = VideoFrame {
+            pts, dts, duration: Duration::from_millis(33), width: w, height: h,
+            format: PixelFormat::I420, key_frame: header.idr, planes, surface: None,
+        };
+        self.picture = Some(CurrentPicture {
+            state: PictureState::new(frame, sps.pic_width_in_mbs as usize, sps.pic_height_in_map_units as usize),
+            header: header.clone(),
+            poc,
+            poc_msb,
+            frame_num_offset,
+            pts,
+            dts,
+            slices: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Initial P reference list (clause 8.2.4.2.1) with modifications applied
+    fn build_ref_list(&self, header: &SliceHeader, sps: &Sps) -> DecoderResult<Vec<usize>> {
+        let max_frame_num = sps.max_frame_num();
+        let refs: Vec<&ReferenceFrame> = self.dpb.iter().collect();
+        let mut short: Vec<usize> = (0..refs.len()).filter(|&i| !refs[i].long_term).collect();
+        short.sort_by_key(|&i| -Self::pic_num(refs[i].frame_num, header.frame_num, max_frame_num));
+        let mut long: Vec<usize> = (0..refs.len()).filter(|&i| refs[i].long_term).collect();
+        long.sort_by_key(|&i| refs[i].frame_num);
+        let mut list: Vec<usize> = short.into_iter().chain(long).collect();
+
+        let mut pred = header.frame_num as i32;
+        for (i, &(idc, value)) in header.ref_list_modifications.iter().enumerate() {
+            let target = if idc < 2 {
+                let diff = value as i32 + 1;
+                let mut no_wrap = if idc == 0 { pred - diff } else { pred + diff };
+                if no_wrap < 0 {
+                    no_wrap += max_frame_num as i32;
+                } else if no_wrap >= max_frame_num as i32 {
+                    no_wrap -= max_frame_num as i32;
+                }
+                pred = no_wrap;
+                let pic_num = if no_wrap > header.frame_num as i32 { no_wrap - max_frame_num as i32 } else { no_wrap };
+                (0..refs.len()).find(|&k| !refs[k].long_term && Self::pic_num(refs[k].frame_num, header.frame_num, max_frame_num) == pic_num)
+            } else {
+                (0..refs.len()).find(|&k| refs[k].long_term && refs[k].frame_num == value)
+            };
+            let target = target.ok_or_else(|| DecoderError::InvalidBitstream("Missing reference picture".into()))?;
+            if let Some(pos) = list.iter().skip(i).position(|&k| k == target) {
+                list.remove(i + pos);
+            }
+            list.insert(i.min(list.len()), target);
+        }
+        list.truncate(header.num_ref_idx_active as usize);
+        Ok(list)
+    }
+
+    fn decode_slice(&mut self, rbsp: &[u8], nal_ref_idc: u8, idr: bool, pts: Duration, dts: Duration, out: &mut Vec<VideoFrame>) -> DecoderResult<()> {
+        let mut r = BitReader::new(&rbsp[1..]);
+        let header = self.parse_slice_header(&mut r, nal_ref_idc, idr)?;
+        if header.first_mb == 0 {
+            self.finish_picture(out);
+        }
+        if self.picture.is_none() {
+            self.start_picture(&header, pts, dts, out)?;
+        }
+
+        let pps = self.pps_map[&header.pps_id].clone();
+        let sps = self.sps_map[&pps.sps_id].clone();
+        let ref_indices = if header.slice_type == SliceType::P { self.build_ref_list(&header, &sps)? } else { Vec::new() };
+        let all: Vec<&ReferenceFrame> = self.dpb.iter().collect();
+        let refs: Vec<&ReferenceFrame> = ref_indices.iter().map(|&i| all[i]).collect();
+
+        let Some(pic) = self.picture.as_mut() else { return Ok(()) };
+        let slice_num = pic.slices.len() as u32;
+        pic.slices.push(header.deblock);
+        let mut ctx = SliceContext {
+            slice_num,
+            is_p: header.slice_type == SliceType::P,
+            qp: pps.pic_init_qp as i32 + header.slice_qp_delta,
+            chroma_qp_offset: pps.chroma_qp_index_offset as i32,
+            constrained_intra_pred: pps.constrained_intra_pred,
+            num_ref_idx_active: header.num_ref_idx_active,
+            refs: refs.iter().map(|f| RefPic { frame: &f.frame, poc: f.poc }).collect(),
+        };
+        if !(0..52).contains(&ctx.qp) {
+            return Err(DecoderError::InvalidBitstream("Slice QP out of range".into()));
+        }
+        // A damaged slice leaves its remaining macroblocks concealed
+        let _ = decode_slice_data(&mut r, &mut pic.state, &mut ctx, header.first_mb as usize);
+        Ok(())
+    }
+
+    /// Reference marking after decoding (clause 8.2.5)
+    fn mark_references(&mut self, pic: &CurrentPicture, frame: VideoFrame) -> bool {
+        let h = &pic.header;
+        let max_frame_num = self.sps_map.get(&self.active_sps_id.unwrap_or_default()).map(|s| s.max_frame_num()).unwrap_or(16);
+        let current = h.frame_num;
+        let mut long_term_idx = if h.idr && h.long_term_reference { Some(0) } else { None };
+        let mut mmco5 = false;
+
+        if !h.idr && !h.mmcos.is_empty() {
+            for op in &h.mmcos {
+                match *op {
+                    Mmco::UnmarkShortTerm(diff) => {
+                        let pic_num = current as i32 - (diff as i32 + 1);
+                        self.dpb.retain(|f| f.long_term || Self::pic_num(f.frame_num, current, max_frame_num) != pic_num);
+                    }
+                    Mmco::UnmarkLongTerm(num) => self.dpb.retain(|f| !(f.long_term && f.frame_num == num)),
+                    Mmco::ShortToLong { difference_of_pic_nums_minus1, long_term_frame_idx } => {
+                        let pic_num = current as i32 - (difference_of_pic_nums_minus1 as i32 + 1);
+                        self.dpb.retain(|f| !(f.long_term && f.frame_num == long_term_frame_idx));
+                        if let Some(f) = self.dpb.iter_mut().find(|f| !f.long_term && Self::pic_num(f.frame_num, current, max_frame_num) == pic_num) {
+                            f.long_term = true;
+                            f.frame_num = long_term_frame_idx;
+                        }
+                    }
+                    Mmco::MaxLongTermIdx(plus1) => {
+                        self.max_long_term_frame_idx = plus1.checked_sub(1);
+                        self.dpb.retain(|f| !f.long_term || (plus1 > 0 && f.frame_num < plus1));
+                    }
+                    Mmco::UnmarkAll => {
+                        self.dpb.clear();
+                        self.max_long_term_frame_idx = None;
+                        mmco5 = true;
+                    }
+                    Mmco::CurrentToLong(idx) if !matches!(self.max_long_term_frame_idx, Some(max) if idx > max) => {
+                        self.dpb.retain(|f| !(f.long_term && f.frame_num == idx));
+                        long_term_idx = Some(idx);
+                    }
+                    Mmco::CurrentToLong(_) => {}
+                }
+            }
+        }
+        // Sliding window is applied by the buffer when it is full
+        let (frame_num, long_term) = match long_term_idx {
+            Some(idx) => (idx, true),
+            None => (if mmco5 { 0 } else { current }, false),
+        };
+        self.dpb.add(ReferenceFrame { frame, poc: if mmco5 { 0 } else { pic.poc }, frame_num, long_term });
+        mmco5
+    }
+
+    /// Deblock, mark and queue the picture in progress
+    fn finish_picture(&mut self, out: &mut Vec<VideoFrame>) {
+        let Some(mut pic) = self.picture.take() else { return };
+        deblock_picture(&mut pic.state, &pic.slices);
+        let sps = match self.active_sps_id.and_then(|id| self.sps_map.get(&id)) {
+            Some(sps) => sps.clone(),
+            None => return,
+        };
+
+        let mut frame = std::mem::replace(&mut pic.state.frame, VideoFrame {
+            pts: pic.pts, dts: pic.dts, duration: Duration::ZERO, width: 0, height: 0,
+            format: PixelFormat::I420, key_frame: false, planes: Vec::new(), surface: None,
+        });
+        frame.pts = pic.pts;
+        frame.dts = pic.dts;
+        let output = Self::crop(&frame, &sps);
+
+        let mut poc = pic.poc;
+        let mut mmco5 = false;
+        if pic.header.nal_ref_idc != 0 {
+            mmco5 = self.mark_references(&pic, frame);
+        }
+        if mmco5 {
+            // Memory reset: earlier pictures precede this one in output order
+            let current = poc;
+            for (p, _) in &mut self.pending_output {
+                *p -= current;
+            }
+            poc = 0;
+            self.prev_poc_msb = 0;
+            self.prev_poc_lsb = 0;
+            self.prev_frame_num = 0;
+            self.prev_frame_num_offset = 0;
+        } else {
+            if pic.header.nal_ref_idc != 0 {
+                self.prev_poc_msb = pic.poc_msb;
+                self.prev_poc_lsb = pic.header.pic_order_cnt_lsb as i32;
+            }
+            self.prev_frame_num = pic.header.frame_num;
+            self.prev_frame_num_offset = pic.frame_num_offset;
+        }
+        self.state.frame_num += 1;
+
+        self.pending_output.push((poc, output));
+        let reorder = if sps.profile_idc == 66 { 0 } else { sps.max_num_ref_frames as usize };
+        while self.pending_output.len() > reorder {
+            out.push(self.pop_output());
+        }
+    }
+
+    fn pop_output(&mut self) -> VideoFrame {
+        let idx = (0..self.pending_output.len()).min_by_key(|&i| self.pending_output[i].0).unwrap_or(0);
+        self.pending_output.remove(idx).1
+    }
+
+    fn drain_output(&mut self, out: &mut Vec<VideoFrame>) {
+        while !self.pending_output.is_empty() {
+            out.push(self.pop_output());
+        }
+    }
+
+    /// Apply SPS frame cropping (4:2:0 crop units are 2 samples)
+    fn crop(frame: &VideoFrame, sps: &Sps) -> VideoFrame {
+        let [left, right, top, bottom] = sps.crop.map(|c| c * 2);
+        if left + right + top + bottom == 0 || left + right >= frame.width || top + bottom >= frame.height {
+            return frame.clone();
+        }
+        let (w, h) = (frame.width - left - right, frame.height - top - bottom);
+        let planes = frame.planes.iter().enumerate().map(|(i, p)| {
+            let div = if i == 0 { 1 } else { 2 };
+            let (x0, y0, pw, ph) = ((left / div) as usize, (top / div) as usize, (w / div) as usize, (h / div) as usize);
+            let mut data = Vec::with_capacity(pw * ph);
+            for row in 0..ph {
+                let start = (y0 + row) * p.stride + x0;
+                data.extend_from_slice(&p.data[start..start + pw]);
+            }
+            Plane { data, stride: pw }
+        }).collect();
+        VideoFrame { width: w, height: h, planes, ..frame.clone() }
+    }
+
+    fn decode_nal(&mut self, nal: &[u8], pts: Duration, dts: Duration, out: &mut Vec<VideoFrame>) -> DecoderResult<()> {
+        if nal.is_empty() { return Ok(()); }
+        let rbsp = Self::nal_to_rbsp(nal);
+        let nal_ref_idc = (rbsp[0] >> 5) & 3;
+        match NalUnitType::try_from(rbsp[0] & 0x1F)? {
+            NalUnitType::Sps => self.parse_sps(&rbsp),
+            NalUnitType::Pps => self.parse_pps(&rbsp),
+            NalUnitType::IdrSlice => self.decode_slice(&rbsp, nal_ref_idc, true, pts, dts, out),
+            NalUnitType::NonIdrSlice => self.decode_slice(&rbsp, nal_ref_idc, false, pts, dts, out),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for H264Decoder { fn default() -> Self { Self::new() } }
+
+impl VideoDecoderTrait for H264Decoder {
+    fn decode(&mut self, packet: &EncodedPacket) -> DecoderResult<Vec<VideoFrame>> {
+        let data = &packet.data;
+        let annex_b = data.starts_with(&[0, 0, 1]) || data.starts_with(&[0, 0, 0, 1]);
+        let nals = match self.nal_length_size {
+            Some(size) if !annex_b => Self::parse_length_prefixed(data, size),
+            None if !annex_b => Self::parse_length_prefixed(data, 4),
+            _ => Self::parse_nal_units(data),
+        };
+        let mut frames = Vec::new();
+        for nal in nals {
+            self.decode_nal(nal, packet.pts, packet.dts, &mut frames)?;
+        }
+        // Containers deliver one access unit per packet
+        self.finish_picture(&mut frames);
+        Ok(frames)
+    }
+    fn flush(&mut self) -> Vec<VideoFrame> {
+        let mut frames = Vec::new();
+        self.finish_picture(&mut frames);
+        self.drain_output(&mut frames);
+        frames
+    }
+    fn reset(&mut self) {
+        self.dpb.clear();
+        self.pending_output.clear();
+        self.picture = None;
+        self.state.frame_num = 0;
+        self.prev_poc_msb = 0;
+        self.prev_poc_lsb = 0;
+        self.prev_frame_num = 0;
+        self.prev_frame_num_offset = 0;
+    }
+    fn capabilities(&self) -> DecoderCaps { DecoderCaps { max_width: 4096, max_height: 2160, formats: vec![PixelFormat::I420], hardware: false } }
+    fn configure(&mut self, extradata: &[u8]) -> DecoderResult<()> {
+        if extradata.starts_with(&[0, 0, 1]) || extradata.starts_with(&[0, 0, 0, 1]) {
+            let mut frames = Vec::new();
+            for nal in Self::parse_nal_units(extradata) {
+                self.decode_nal(nal, Duration::ZERO, Duration::ZERO, &mut frames)?;
+            }
+            return Ok(());
+        }
+        // AVCDecoderConfigurationRecord
+        if extradata.len() < 7 || extradata[0] != 1 {
+            return Err(DecoderError::InvalidBitstream("Invalid avcC".into()));
+        }
+        self.nal_length_size = Some((extradata[4] & 3) as usize + 1);
+        let mut pos = 5;
+        let mut frames = Vec::new();
+        for mask in [0x1F, 0xFF] {
+            let count = extradata.get(pos).copied().unwrap_or(0) & mask;
+            pos += 1;
+            for _ in 0..count {
+                if pos + 2 > extradata.len() { return Err(DecoderError::NeedMoreData); }
+                let len = u16::from_be_bytes([extradata[pos], extradata[pos + 1]]) as usize;
+                pos += 2;
+                if pos + len > extradata.len() { return Err(DecoderError::NeedMoreData); }
+                self.decode_nal(&extradata[pos..pos + len], Duration::ZERO, Duration::ZERO, &mut frames)?;
+                pos += len;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal bit writer for building test bitstreams
+    struct Bits { bytes: Vec<u8>, bits: usize }
+
+    impl Bits {
+        fn new(header: u8) -> Self { Self { bytes: vec![header], bits: 8 } }
+        fn put(&mut self, value: u32, n: usize) {
+            for i in (0..n).rev() {
+                if self.bits.is_multiple_of(8) { self.bytes.push(0); }
+                if (value >> i) & 1 == 1 { *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bits % 8); }
+                self.bits += 1;
+            }
+        }
+        fn ue(&mut self, v: u32) {
+            let len = 32 - (v + 1).leading_zeros() as usize;
+            self.put(0, len - 1);
+            self.put(v + 1, len);
+        }
+        fn align(&mut self) { while !self.bits.is_multiple_of(8) { self.put(0, 1); } }
+        fn finish(mut self) -> Vec<u8> { self.put(1, 1); self.align(); self.bytes }
+    }
+
+    fn annex_b(nals: &[Vec<u8>]) -> Vec<u8> {
+        nals.iter().flat_map(|n| [0, 0, 0, 1].into_iter().chain(n.iter().copied())).collect()
+    }
+
+    fn parameter_sets() -> Vec<Vec<u8>> {
+        let mut sps = Bits::new(0x67);
+        sps.put(66, 8); sps.put(0, 8); sps.put(30, 8);
+        sps.ue(0); sps.ue(0); sps.ue(0); sps.ue(0); sps.ue(1);
+        sps.put(0, 1); sps.ue(0); sps.ue(0); // one macroblock
+        sps.put(1, 1); sps.put(1, 1); sps.put(0, 1); sps.put(0, 1);
+        let mut pps = Bits::new(0x68);
+        pps.ue(0); pps.ue(0); pps.put(0, 1); pps.put(0, 1); pps.ue(0); pps.ue(0); pps.ue(0);
+        pps.put(0, 3); pps.ue(0); pps.ue(0); pps.ue(0);
+        pps.put(1, 1); pps.put(0, 1); pps.put(0, 1);
+        vec![sps.finish(), pps.finish()]
+    }
+
+    #[test]
+    fn test_decoder() { let d = H264Decoder::new(); assert!(d.sps_map.is_empty()); assert_eq!(d.capabilities().max_width, 4096); }
+
+    #[test]
+    fn test_emulation_prevention() {
+        assert_eq!(H264Decoder::nal_to_rbsp(&[0x65, 0, 0, 3, 1]), vec![0x65, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_pcm_then_skip() {
+        // IDR with a single I_PCM macroblock, deblocking disabled
+        let mut idr = Bits::new(0x65);
+        idr.ue(0); idr.ue(7); idr.ue(0); idr.put(0, 4); idr.ue(0); idr.put(0, 4);
+        idr.put(0, 2); idr.ue(0); idr.ue(1);
+        idr.ue(25);
+        idr.align();
+        for _ in 0..256 { idr.put(200, 8); }
+        for _ in 0..128 { idr.put(100, 8); }
+        let mut nals = parameter_sets();
+        nals.push(idr.finish());
+
+        let mut dec = H264Decoder::new();
+        let packet = EncodedPacket { data: annex_b(&nals), pts: Duration::ZERO, dts: Duration::ZERO, is_key: true };
+        let frames = dec.decode(&packet).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!((frames[0].width, frames[0].height), (16, 16));
+        assert!(frames[0].planes[0].data.iter().all(|&v| v == 200));
+        assert!(frames[0].planes[1].data.iter().all(|&v| v == 100));
+
+        // P picture made of one skipped macroblock copies the reference
+        let mut p = Bits::new(0x41);
+        p.ue(0); p.ue(5); p.ue(0); p.put(1, 4); p.put(2, 4);
+        p.put(0, 1); p.put(0, 1); p.put(0, 1); p.ue(0); p.ue(1);
+        p.ue(1);
+        let packet = EncodedPacket { data: annex_b(&[p.finish()]), pts: Duration::from_millis(40), dts: Duration::from_millis(40), is_key: false };
+        let frames = dec.decode(&packet).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].pts, Duration::from_millis(40));
+        assert!(frames[0].planes[0].data.iter().all(|&v| v == 200));
+    }
+
+    #[test]
+    fn test_avcc_configuration() {
+        let sets = parameter_sets();
+        let mut avcc = vec![1, 66, 0, 30, 0xFF, 0xE1];
+        avcc.extend((sets[0].len() as u16).to_be_bytes());
+        avcc.extend(&sets[0]);
+        avcc.push(1);
+        avcc.extend((sets[1].len() as u16).to_be_bytes());
+        avcc.extend(&sets[1]);
+
+        let mut dec = H264Decoder::new();
+        dec.configure(&avcc).unwrap();
+        assert_eq!(dec.nal_length_size, Some(4));
+        assert!(dec.sps_map.contains_key(&0) && dec.pps_map.contains_key(&0));
+    }
+}
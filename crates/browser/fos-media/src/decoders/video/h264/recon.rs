@@ -0,0 +1,492 @@
+//! H.264 Reconstruction
+//!
+//! Intra prediction, inverse transforms and motion-compensated inter prediction (clause 8.3 - 8.5).
+
+/// 4x4 frame zig-zag scan, scan index -> raster index (x + 4y)
+pub const ZIGZAG_4X4: [usize; 16] = [0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15];
+
+/// Dequantisation scale v[qP % 6][position class] (flat scaling matrices)
+const LEVEL_SCALE: [[i32; 3]; 6] = [
+    [10, 16, 13], [11, 18, 14], [13, 20, 16],
+    [14, 23, 18], [16, 25, 20], [18, 29, 23],
+];
+
+/// QPc for qPi >= 30 (table 8-15)
+const CHROMA_QP: [u8; 22] = [29, 30, 31, 32, 32, 33, 34, 34, 35, 35, 36, 36, 37, 37, 37, 38, 38, 38, 39, 39, 39, 39];
+
+#[inline]
+pub fn clip_pixel(v: i32) -> u8 {
+    v.clamp(0, 255) as u8
+}
+
+/// Chroma QP for a luma QP and chroma_qp_index_offset
+pub fn chroma_qp(qp: i32, offset: i32) -> i32 {
+    let qpi = (qp + offset).clamp(0, 51);
+    if qpi < 30 { qpi } else { CHROMA_QP[(qpi - 30) as usize] as i32 }
+}
+
+fn level_scale(qp: i32, raster: usize) -> i32 {
+    let (x, y) = (raster % 4, raster / 4);
+    let class = match (x % 2, y % 2) {
+        (0, 0) => 0,
+        (1, 1) => 1,
+        _ => 2,
+    };
+    LEVEL_SCALE[(qp % 6) as usize][class]
+}
+
+/// Inverse scan and dequantise a 4x4 block. With `skip_dc` the DC is left
+/// untouched for the caller to fill from the separately coded DC block.
+pub fn dequant_4x4(scan: &[i32; 16], qp: i32, skip_dc: bool) -> [i32; 16] {
+    let mut out = [0i32; 16];
+    for (k, &level) in scan.iter().enumerate() {
+        if level == 0 || (skip_dc && k == 0) {
+            continue;
+        }
+        let pos = ZIGZAG_4X4[k];
+        out[pos] = (level * level_scale(qp, pos)) << (qp / 6);
+    }
+    out
+}
+
+/// Intra_16x16 luma DC: inverse Hadamard and dequantisation. Output is raster
+/// (x + 4y) over the macroblock's 4x4 blocks.
+pub fn luma_dc_dequant(scan: &[i32; 16], qp: i32) -> [i32; 16] {
+    let mut c = [0i32; 16];
+    for (k, &level) in scan.iter().enumerate() {
+        c[ZIGZAG_4X4[k]] = level;
+    }
+    let mut t = [0i32; 16];
+    for i in 0..4 {
+        let (a, b, cc, d) = (c[i * 4], c[i * 4 + 1], c[i * 4 + 2], c[i * 4 + 3]);
+        t[i * 4] = a + b + cc + d;
+        t[i * 4 + 1] = a + b - cc - d;
+        t[i * 4 + 2] = a - b - cc + d;
+        t[i * 4 + 3] = a - b + cc - d;
+    }
+    let scale = 16 * LEVEL_SCALE[(qp % 6) as usize][0];
+    let mut out = [0i32; 16];
+    for j in 0..4 {
+        let (a, b, cc, d) = (t[j], t[4 + j], t[8 + j], t[12 + j]);
+        let f = [a + b + cc + d, a + b - cc - d, a - b - cc + d, a - b + cc - d];
+        for (i, v) in f.iter().enumerate() {
+            out[i * 4 + j] = if qp >= 36 {
+                (v * scale) << (qp / 6 - 6)
+            } else {
+                (v * scale + (1 << (5 - qp / 6))) >> (6 - qp / 6)
+            };
+        }
+    }
+    out
+}
+
+/// Chroma DC (2x2) inverse transform and dequantisation, raster order
+pub fn chroma_dc_dequant(c: &[i32; 4], qp: i32) -> [i32; 4] {
+    let f = [
+        c[0] + c[1] + c[2] + c[3],
+        c[0] - c[1] + c[2] - c[3],
+        c[0] + c[1] - c[2] - c[3],
+        c[0] - c[1] - c[2] + c[3],
+    ];
+    let scale = 16 * LEVEL_SCALE[(qp % 6) as usize][0];
+    f.map(|v| ((v * scale) << (qp / 6)) >> 5)
+}
+
+/// Inverse 4x4 integer transform, adding the residual to `dst` at (x, y)
+pub fn idct_add_4x4(block: &[i32; 16], dst: &mut [u8], stride: usize, x: usize, y: usize) {
+    let mut t = [0i32; 16];
+    for i in 0..4 {
+        let r = &block[i * 4..i * 4 + 4];
+        let e0 = r[0] + r[2];
+        let e1 = r[0] - r[2];
+        let e2 = (r[1] >> 1) - r[3];
+        let e3 = r[1] + (r[3] >> 1);
+        t[i * 4] = e0 + e3;
+        t[i * 4 + 1] = e1 + e2;
+        t[i * 4 + 2] = e1 - e2;
+        t[i * 4 + 3] = e0 - e3;
+    }
+    for j in 0..4 {
+        let e0 = t[j] + t[8 + j];
+        let e1 = t[j] - t[8 + j];
+        let e2 = (t[4 + j] >> 1) - t[12 + j];
+        let e3 = t[4 + j] + (t[12 + j] >> 1);
+        let col = [e0 + e3, e1 + e2, e1 - e2, e0 - e3];
+        for (i, v) in col.iter().enumerate() {
+            let idx = (y + i) * stride + x + j;
+            dst[idx] = clip_pixel(dst[idx] as i32 + ((v + 32) >> 6));
+        }
+    }
+}
+
+/// Which neighbouring samples may be used for intra prediction
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IntraNeighbors {
+    pub left: bool,
+    pub top: bool,
+    pub top_right: bool,
+    pub top_left: bool,
+}
+
+/// Neighbouring samples of an N x N block
+struct Edge {
+    /// p[x, -1] for x in 0..2N
+    top: [i32; 32],
+    /// p[-1, y] for y in 0..N
+    left: [i32; 16],
+    top_left: i32,
+}
+
+impl Edge {
+    fn load(plane: &[u8], stride: usize, x: usize, y: usize, n: usize, top_n: usize, avail: IntraNeighbors) -> Self {
+        let mut edge = Edge { top: [128; 32], left: [128; 16], top_left: 128 };
+        if avail.top {
+            for i in 0..n {
+                edge.top[i] = plane[(y - 1) * stride + x + i] as i32;
+            }
+            for i in n..top_n {
+                edge.top[i] = if avail.top_right {
+                    plane[(y - 1) * stride + x + i] as i32
+                } else {
+                    edge.top[n - 1]
+                };
+            }
+        }
+        if avail.left {
+            for i in 0..n {
+                edge.left[i] = plane[(y + i) * stride + x - 1] as i32;
+            }
+        }
+        if avail.top_left {
+            edge.top_left = plane[(y - 1) * stride + x - 1] as i32;
+        }
+        edge
+    }
+
+    /// p[x, y] with x == -1 or y == -1
+    fn p(&self, x: i32, y: i32) -> i32 {
+        match (x, y) {
+            (-1, -1) => self.top_left,
+            (-1, y) => self.left[y as usize],
+            (x, _) => self.top[x as usize],
+        }
+    }
+}
+
+fn dc_value(edge: &Edge, n: usize, avail: IntraNeighbors) -> u8 {
+    let shift = n.trailing_zeros();
+    let top: i32 = edge.top[..n].iter().sum();
+    let left: i32 = edge.left[..n].iter().sum();
+    match (avail.top, avail.left) {
+        (true, true) => ((top + left + n as i32) >> (shift + 1)) as u8,
+        (true, false) => ((top + (n as i32 >> 1)) >> shift) as u8,
+        (false, true) => ((left + (n as i32 >> 1)) >> shift) as u8,
+        (false, false) => 128,
+    }
+}
+
+/// Intra_4x4 prediction (clause 8.3.1.2) into `plane` at (x, y)
+pub fn predict_intra4x4(plane: &mut [u8], stride: usize, x: usize, y: usize, mode: u8, avail: IntraNeighbors) {
+    let e = Edge::load(plane, stride, x, y, 4, 8, avail);
+    let dc = dc_value(&e, 4, avail);
+    for yy in 0..4i32 {
+        for xx in 0..4i32 {
+            let v = match mode {
+                0 => e.p(xx, -1),
+                1 => e.p(-1, yy),
+                2 => dc as i32,
+                3 => {
+                    if xx == 3 && yy == 3 {
+                        (e.p(6, -1) + 3 * e.p(7, -1) + 2) >> 2
+                    } else {
+                        (e.p(xx + yy, -1) + 2 * e.p(xx + yy + 1, -1) + e.p(xx + yy + 2, -1) + 2) >> 2
+                    }
+                }
+                4 => {
+                    if xx > yy {
+                        (e.p(xx - yy - 2, -1) + 2 * e.p(xx - yy - 1, -1) + e.p(xx - yy, -1) + 2) >> 2
+                    } else if xx < yy {
+                        (e.p(-1, yy - xx - 2) + 2 * e.p(-1, yy - xx - 1) + e.p(-1, yy - xx) + 2) >> 2
+                    } else {
+                        (e.p(0, -1) + 2 * e.p(-1, -1) + e.p(-1, 0) + 2) >> 2
+                    }
+                }
+                5 => {
+                    let z = 2 * xx - yy;
+                    if z >= 0 && z % 2 == 0 {
+                        (e.p(xx - (yy >> 1) - 1, -1) + e.p(xx - (yy >> 1), -1) + 1) >> 1
+                    } else if z >= 0 {
+                        (e.p(xx - (yy >> 1) - 2, -1) + 2 * e.p(xx - (yy >> 1) - 1, -1) + e.p(xx - (yy >> 1), -1) + 2) >> 2
+                    } else if z == -1 {
+                        (e.p(-1, 0) + 2 * e.p(-1, -1) + e.p(0, -1) + 2) >> 2
+                    } else {
+                        (e.p(-1, yy - 1) + 2 * e.p(-1, yy - 2) + e.p(-1, yy - 3) + 2) >> 2
+                    }
+                }
+                6 => {
+                    let z = 2 * yy - xx;
+                    if z >= 0 && z % 2 == 0 {
+                        (e.p(-1, yy - (xx >> 1) - 1) + e.p(-1, yy - (xx >> 1)) + 1) >> 1
+                    } else if z >= 0 {
+                        (e.p(-1, yy - (xx >> 1) - 2) + 2 * e.p(-1, yy - (xx >> 1) - 1) + e.p(-1, yy - (xx >> 1)) + 2) >> 2
+                    } else if z == -1 {
+                        (e.p(-1, 0) + 2 * e.p(-1, -1) + e.p(0, -1) + 2) >> 2
+                    } else {
+                        (e.p(xx - 1, -1) + 2 * e.p(xx - 2, -1) + e.p(xx - 3, -1) + 2) >> 2
+                    }
+                }
+                7 => {
+                    let i = xx + (yy >> 1);
+                    if yy % 2 == 0 {
+                        (e.p(i, -1) + e.p(i + 1, -1) + 1) >> 1
+                    } else {
+                        (e.p(i, -1) + 2 * e.p(i + 1, -1) + e.p(i + 2, -1) + 2) >> 2
+                    }
+                }
+                _ => {
+                    let z = xx + 2 * yy;
+                    let i = yy + (xx >> 1);
+                    match z {
+                        0 | 2 | 4 => (e.p(-1, i) + e.p(-1, i + 1) + 1) >> 1,
+                        1 | 3 => (e.p(-1, i) + 2 * e.p(-1, i + 1) + e.p(-1, i + 2) + 2) >> 2,
+                        5 => (e.p(-1, 2) + 3 * e.p(-1, 3) + 2) >> 2,
+                        _ => e.p(-1, 3),
+                    }
+                }
+            };
+            plane[(y + yy as usize) * stride + x + xx as usize] = v as u8;
+        }
+    }
+}
+
+/// Plane prediction shared by Intra_16x16 and chroma
+fn predict_plane(plane: &mut [u8], stride: usize, x: usize, y: usize, n: usize, e: &Edge) {
+    let half = n as i32 / 2;
+    let (mut h, mut v) = (0, 0);
+    for i in 0..half {
+        h += (i + 1) * (e.p(half + i, -1) - e.p(half - 2 - i, -1));
+        v += (i + 1) * (e.p(-1, half + i) - e.p(-1, half - 2 - i));
+    }
+    let a = 16 * (e.p(-1, n as i32 - 1) + e.p(n as i32 - 1, -1));
+    let (b, c) = if n == 16 {
+        ((5 * h + 32) >> 6, (5 * v + 32) >> 6)
+    } else {
+        ((34 * h + 32) >> 6, (34 * v + 32) >> 6)
+    };
+    for yy in 0..n as i32 {
+        for xx in 0..n as i32 {
+            let val = (a + b * (xx - (half - 1)) + c * (yy - (half - 1)) + 16) >> 5;
+            plane[(y + yy as usize) * stride + x + xx as usize] = clip_pixel(val);
+        }
+    }
+}
+
+/// Intra_16x16 prediction (clause 8.3.3)
+pub fn predict_intra16x16(plane: &mut [u8], stride: usize, x: usize, y: usize, mode: u8, avail: IntraNeighbors) {
+    let e = Edge::load(plane, stride, x, y, 16, 16, avail);
+    match mode {
+        3 => predict_plane(plane, stride, x, y, 16, &e),
+        _ => {
+            let dc = dc_value(&e, 16, avail) as i32;
+            for yy in 0..16 {
+                for xx in 0..16 {
+                    let v = match mode {
+                        0 => e.top[xx],
+                        1 => e.left[yy],
+                        _ => dc,
+                    };
+                    plane[(y + yy) * stride + x + xx] = v as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Intra chroma prediction for one 8x8 chroma block (clause 8.3.4)
+pub fn predict_chroma(plane: &mut [u8], stride: usize, x: usize, y: usize, mode: u8, avail: IntraNeighbors) {
+    let e = Edge::load(plane, stride, x, y, 8, 8, avail);
+    match mode {
+        3 => predict_plane(plane, stride, x, y, 8, &e),
+        0 => {
+            // DC per 4x4 chroma block
+            for by in 0..2 {
+                for bx in 0..2 {
+                    let top: i32 = e.top[bx * 4..bx * 4 + 4].iter().sum();
+                    let left: i32 = e.left[by * 4..by * 4 + 4].iter().sum();
+                    let both = (top + left + 4) >> 3;
+                    let t = (top + 2) >> 2;
+                    let l = (left + 2) >> 2;
+                    let dc = match (bx, by) {
+                        (1, 0) if avail.top => t,
+                        (1, 0) if avail.left => l,
+                        (0, 1) if avail.left => l,
+                        (0, 1) if avail.top => t,
+                        (1, 0) | (0, 1) => 128,
+                        _ => match (avail.top, avail.left) {
+                            (true, true) => both,
+                            (true, false) => t,
+                            (false, true) => l,
+                            (false, false) => 128,
+                        },
+                    };
+                    for yy in 0..4 {
+                        for xx in 0..4 {
+                            plane[(y + by * 4 + yy) * stride + x + bx * 4 + xx] = dc as u8;
+                        }
+                    }
+                }
+            }
+        }
+        _ => {
+            for yy in 0..8 {
+                for xx in 0..8 {
+                    let v = if mode == 1 { e.left[yy] } else { e.top[xx] };
+                    plane[(y + yy) * stride + x + xx] = v as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Read-only view of a reference plane with edge clamping
+#[derive(Debug, Clone, Copy)]
+pub struct RefPlane<'a> {
+    pub data: &'a [u8],
+    pub stride: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl RefPlane<'_> {
+    #[inline]
+    fn at(&self, x: i32, y: i32) -> i32 {
+        let x = x.clamp(0, self.width as i32 - 1) as usize;
+        let y = y.clamp(0, self.height as i32 - 1) as usize;
+        self.data[y * self.stride + x] as i32
+    }
+
+    /// Unclipped 6-tap half-sample value between (x, y) and (x + 1, y)
+    fn tap_h(&self, x: i32, y: i32) -> i32 {
+        self.at(x - 2, y) - 5 * self.at(x - 1, y) + 20 * self.at(x, y)
+            + 20 * self.at(x + 1, y) - 5 * self.at(x + 2, y) + self.at(x + 3, y)
+    }
+
+    fn tap_v(&self, x: i32, y: i32) -> i32 {
+        self.at(x, y - 2) - 5 * self.at(x, y - 1) + 20 * self.at(x, y)
+            + 20 * self.at(x, y + 1) - 5 * self.at(x, y + 2) + self.at(x, y + 3)
+    }
+
+    fn half_h(&self, x: i32, y: i32) -> i32 {
+        clip_pixel((self.tap_h(x, y) + 16) >> 5) as i32
+    }
+
+    fn half_v(&self, x: i32, y: i32) -> i32 {
+        clip_pixel((self.tap_v(x, y) + 16) >> 5) as i32
+    }
+
+    fn center(&self, x: i32, y: i32) -> i32 {
+        let j1 = self.tap_h(x, y - 2) - 5 * self.tap_h(x, y - 1) + 20 * self.tap_h(x, y)
+            + 20 * self.tap_h(x, y + 1) - 5 * self.tap_h(x, y + 2) + self.tap_h(x, y + 3);
+        clip_pixel((j1 + 512) >> 10) as i32
+    }
+
+    /// Luma sample at quarter-sample position (clause 8.4.2.2.1)
+    fn luma(&self, x: i32, y: i32, fx: i32, fy: i32) -> i32 {
+        let avg = |a: i32, b: i32| (a + b + 1) >> 1;
+        match (fx, fy) {
+            (0, 0) => self.at(x, y),
+            (1, 0) => avg(self.at(x, y), self.half_h(x, y)),
+            (2, 0) => self.half_h(x, y),
+            (3, 0) => avg(self.at(x + 1, y), self.half_h(x, y)),
+            (0, 1) => avg(self.at(x, y), self.half_v(x, y)),
+            (0, 2) => self.half_v(x, y),
+            (0, 3) => avg(self.at(x, y + 1), self.half_v(x, y)),
+            (1, 1) => avg(self.half_h(x, y), self.half_v(x, y)),
+            (3, 1) => avg(self.half_h(x, y), self.half_v(x + 1, y)),
+            (1, 3) => avg(self.half_v(x, y), self.half_h(x, y + 1)),
+            (3, 3) => avg(self.half_v(x + 1, y), self.half_h(x, y + 1)),
+            (2, 1) => avg(self.half_h(x, y), self.center(x, y)),
+            (2, 3) => avg(self.center(x, y), self.half_h(x, y + 1)),
+            (1, 2) => avg(self.half_v(x, y), self.center(x, y)),
+            (3, 2) => avg(self.center(x, y), self.half_v(x + 1, y)),
+            _ => self.center(x, y),
+        }
+    }
+}
+
+/// Luma motion compensation of a w x h block at (x, y); mv in quarter samples
+#[allow(clippy::too_many_arguments)]
+pub fn predict_luma(
+    reference: RefPlane, dst: &mut [u8], stride: usize,
+    x: usize, y: usize, w: usize, h: usize, mv: [i16; 2],
+) {
+    let (fx, fy) = ((mv[0] & 3) as i32, (mv[1] & 3) as i32);
+    let (ox, oy) = (x as i32 + (mv[0] as i32 >> 2), y as i32 + (mv[1] as i32 >> 2));
+    for yy in 0..h {
+        for xx in 0..w {
+            let v = reference.luma(ox + xx as i32, oy + yy as i32, fx, fy);
+            dst[(y + yy) * stride + x + xx] = v as u8;
+        }
+    }
+}
+
+/// Chroma motion compensation; (x, y, w, h) in chroma samples, mv in quarter luma samples
+#[allow(clippy::too_many_arguments)]
+pub fn predict_chroma_mc(
+    reference: RefPlane, dst: &mut [u8], stride: usize,
+    x: usize, y: usize, w: usize, h: usize, mv: [i16; 2],
+) {
+    let (fx, fy) = ((mv[0] & 7) as i32, (mv[1] & 7) as i32);
+    let (ox, oy) = (x as i32 + (mv[0] as i32 >> 3), y as i32 + (mv[1] as i32 >> 3));
+    for yy in 0..h as i32 {
+        for xx in 0..w as i32 {
+            let (sx, sy) = (ox + xx, oy + yy);
+            let v = ((8 - fx) * (8 - fy) * reference.at(sx, sy)
+                + fx * (8 - fy) * reference.at(sx + 1, sy)
+                + (8 - fx) * fy * reference.at(sx, sy + 1)
+                + fx * fy * reference.at(sx + 1, sy + 1)
+                + 32) >> 6;
+            dst[(y + yy as usize) * stride + x + xx as usize] = v as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idct_dc_only() {
+        // A lone DC of 64 adds (64 + 32) >> 6 = 1 to each sample
+        let mut block = [0i32; 16];
+        block[0] = 64;
+        let mut dst = [10u8; 16];
+        idct_add_4x4(&block, &mut dst, 4, 0, 0);
+        assert!(dst.iter().all(|&v| v == 11));
+    }
+
+    #[test]
+    fn test_intra_dc_prediction() {
+        let mut plane = vec![0u8; 8 * 8];
+        for i in 0..8 {
+            plane[3 * 8 + i] = 100; // row above the block at (4, 4)
+            plane[i * 8 + 3] = 50; // column left of it
+        }
+        let avail = IntraNeighbors { left: true, top: true, top_right: false, top_left: true };
+        predict_intra4x4(&mut plane, 8, 4, 4, 2, avail);
+        assert_eq!(plane[4 * 8 + 4], 75);
+        assert_eq!(plane[7 * 8 + 7], 75);
+    }
+
+    #[test]
+    fn test_integer_motion_compensation() {
+        let data: Vec<u8> = (0..64).collect();
+        let reference = RefPlane { data: &data, stride: 8, width: 8, height: 8 };
+        let mut dst = vec![0u8; 64];
+        predict_luma(reference, &mut dst, 8, 0, 0, 4, 4, [4, 8]);
+        // One sample right, two down
+        assert_eq!(dst[0], data[2 * 8 + 1]);
+        assert_eq!(chroma_qp(29, 0), 29);
+        assert_eq!(chroma_qp(51, 0), 39);
+    }
+}
@@ -179,6 +179,7 @@ impl H265Decoder {
                 Plane { data: vec![128; uv_size], stride: (w/2) as usize },
                 Plane { data: vec![128; uv_size], stride: (w/2) as usize },
             ],
+            surface: None,
         };
         if is_idr { self.dpb.clear(); }
         self.dpb.add(ReferenceFrame { frame: frame.clone(), poc: self.poc, frame_num: self.state.frame_num as u32, long_term: false });
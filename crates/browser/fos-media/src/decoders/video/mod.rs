@@ -65,6 +65,20 @@ impl DecodedPictureBuffer {
         self.frames.iter().find(|f| f.frame_num == frame_num)
     }
     
+    /// Iterate stored references, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &ReferenceFrame> {
+        self.frames.iter()
+    }
+    
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut ReferenceFrame> {
+        self.frames.iter_mut()
+    }
+    
+    /// Keep only references matching the predicate
+    pub fn retain(&mut self, f: impl FnMut(&ReferenceFrame) -> bool) {
+        self.frames.retain(f);
+    }
+    
     /// Clear all references
     pub fn clear(&mut self) {
         self.frames.clear();
@@ -114,7 +128,7 @@ impl<'a> BitReader<'a> {
             let bits_in_byte = 8 - self.bit_pos;
             let bits_to_read = bits_remaining.min(bits_in_byte);
             
-            let mask = (1u8 << bits_to_read) - 1;
+            let mask = ((1u16 << bits_to_read) - 1) as u8;
             let shift = bits_in_byte - bits_to_read;
             let bits = (self.data[self.byte_pos] >> shift) & mask;
             
@@ -170,6 +184,11 @@ impl<'a> BitReader<'a> {
         Ok(())
     }
     
+    /// Bits consumed so far
+    pub fn position(&self) -> usize {
+        self.byte_pos * 8 + self.bit_pos as usize
+    }
+    
     /// Check if aligned to byte boundary
     pub fn is_byte_aligned(&self) -> bool {
         self.bit_pos == 0
@@ -196,6 +215,16 @@ impl<'a> BitReader<'a> {
     pub fn has_more_data(&self) -> bool {
         self.byte_pos < self.data.len()
     }
+    
+    /// Whether syntax elements remain before the RBSP stop bit
+    pub fn more_rbsp_data(&self) -> bool {
+        let Some(last) = self.data.iter().rposition(|&b| b != 0) else {
+            return false;
+        };
+        // Bit position of the stop bit (last set bit), counted from the MSB
+        let stop = last * 8 + 7 - self.data[last].trailing_zeros() as usize;
+        self.byte_pos * 8 + (self.bit_pos as usize) < stop
+    }
 }
 
 /// Common video decoder state
@@ -275,6 +304,16 @@ mod tests {
         assert_eq!(reader.read_ue().unwrap(), 2);
     }
     
+    #[test]
+    fn test_more_rbsp_data() {
+        // One payload bit, then the stop bit and alignment zeros
+        let data = [0b1100_0000];
+        let mut reader = BitReader::new(&data);
+        assert!(reader.more_rbsp_data());
+        reader.read_bit().unwrap();
+        assert!(!reader.more_rbsp_data());
+    }
+    
     #[test]
     fn test_dpb() {
         let mut dpb = DecodedPictureBuffer::new(4);
@@ -289,6 +328,7 @@ mod tests {
             format: PixelFormat::I420,
             planes: vec![],
             key_frame: true,
+            surface: None,
         };
         
         dpb.add(ReferenceFrame {
@@ -1,6 +1,11 @@
 //! VP8 Decoder
 //!
 //! Pure-Rust implementation of VP8 video decoding.
+//!
+//! Frame headers and reference updates are parsed, but macroblocks are
+//! not decoded yet: every picture comes out mid-grey. Until they are, the
+//! codec registry does not list VP8, so pages only get VP8 from a platform
+//! decoder.
 
 use super::{BitReader, DecodedPictureBuffer, ReferenceFrame, VideoDecoderState};
 use crate::decoders::{
@@ -89,7 +94,7 @@ impl Vp8Decoder {
         let y_size = (w * h) as usize;
         let uv_size = y_size / 4;
         
-        // Simulated decode - real impl would decode macroblocks
+        // Macroblocks are not decoded yet: the picture is mid-grey
         let frame = VideoFrame {
             pts, dts, duration: Duration::from_millis(33), width: w, height: h,
             format: PixelFormat::I420, key_frame: header.key_frame,
@@ -98,6 +103,7 @@ impl Vp8Decoder {
                 Plane { data: vec![128; uv_size], stride: (w/2) as usize },
                 Plane { data: vec![128; uv_size], stride: (w/2) as usize },
             ],
+            surface: None,
         };
         
         // Update reference frames
@@ -1,28 +1,54 @@
 //! VP9 Decoder
 //!
 //! Pure-Rust implementation of VP9 video decoding.
+//!
+//! Parses superframes, the complete uncompressed header and the start of the
+//! compressed header, and maintains the eight reference slots so that frame
+//! sizes, `show_existing_frame` and hidden (alt-ref) frames behave as in a
+//! conforming decoder. Coefficient and mode decoding is not implemented yet:
+//! intra frames are emitted as mid-grey pictures and inter frames repeat
+//! their LAST reference. Until it is, the codec registry does not list VP9,
+//! so pages only get VP9 from a platform decoder.
 
-use super::{DecodedPictureBuffer, ReferenceFrame, VideoDecoderState};
+use super::{BitReader, VideoDecoderState};
 use crate::decoders::{
     VideoFrame, EncodedPacket, DecoderCaps, DecoderResult, DecoderError,
     VideoDecoderTrait, PixelFormat, Plane,
 };
 use std::time::Duration;
 
+const SYNC_CODE: u32 = 0x49_83_42;
+
 /// VP9 Frame types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Vp9FrameType { KeyFrame, InterFrame }
 
 /// VP9 Color space
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Vp9ColorSpace { Unknown, Bt601, Bt709, Smpte170, Smpte240, Bt2020, Reserved, Srgb }
 
+impl From<u32> for Vp9ColorSpace {
+    fn from(v: u32) -> Self {
+        match v {
+            1 => Self::Bt601, 2 => Self::Bt709, 3 => Self::Smpte170, 4 => Self::Smpte240,
+            5 => Self::Bt2020, 6 => Self::Reserved, 7 => Self::Srgb, _ => Self::Unknown,
+        }
+    }
+}
+
+/// Transform size limit signalled in the compressed header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Vp9TxMode { #[default] Only4x4, Allow8x8, Allow16x16, Allow32x32, TxModeSelect }
+
 /// VP9 Frame Header
 #[derive(Debug, Clone)]
 pub struct Vp9FrameHeader {
     pub frame_type: Vp9FrameType,
     pub show_frame: bool,
+    /// Slot to display when `show_existing_frame` is set
+    pub show_existing_frame: Option<u8>,
     pub error_resilient: bool,
+    pub intra_only: bool,
     pub width: u32,
     pub height: u32,
     pub render_width: u32,
@@ -36,55 +62,126 @@ pub struct Vp9FrameHeader {
     pub ref_frame_idx: [u8; 3],
     pub ref_frame_sign_bias: [bool; 4],
     pub allow_high_precision_mv: bool,
+    /// Interpolation filter (4 = switchable)
     pub interp_filter: u8,
+    pub refresh_frame_context: bool,
+    pub frame_context_idx: u8,
+    pub loop_filter_level: u8,
+    pub sharpness: u8,
+    pub base_q_idx: u8,
+    pub lossless: bool,
+    pub tile_cols_log2: u8,
+    pub tile_rows_log2: u8,
+    /// Size of the compressed header in bytes
+    pub header_size: usize,
+    /// Offset of the compressed header within the frame
+    pub uncompressed_size: usize,
+    pub tx_mode: Vp9TxMode,
+}
+
+/// Boolean (arithmetic) decoder used for the compressed header and tile data
+#[derive(Debug)]
+pub struct BoolDecoder<'a> {
+    data: &'a [u8],
+    bit_pos: usize,
+    value: u32,
+    range: u32,
+}
+
+impl<'a> BoolDecoder<'a> {
+    /// Initialise over `data`; the first decoded bool is the zero marker bit
+    pub fn new(data: &'a [u8]) -> DecoderResult<Self> {
+        if data.is_empty() {
+            return Err(DecoderError::NeedMoreData);
+        }
+        let mut d = Self { data, bit_pos: 8, value: data[0] as u32, range: 255 };
+        if d.read_bool(128) {
+            return Err(DecoderError::InvalidBitstream("Invalid VP9 bool decoder marker".into()));
+        }
+        Ok(d)
+    }
+
+    fn next_bit(&mut self) -> u32 {
+        let bit = self.data.get(self.bit_pos / 8).map_or(0, |b| (b >> (7 - self.bit_pos % 8)) & 1);
+        self.bit_pos += 1;
+        bit as u32
+    }
+
+    /// Decode one bool whose probability of being zero is `prob`/256
+    pub fn read_bool(&mut self, prob: u8) -> bool {
+        let split = 1 + (((self.range - 1) * prob as u32) >> 8);
+        let bit = if self.value < split {
+            self.range = split;
+            false
+        } else {
+            self.range -= split;
+            self.value -= split;
+            true
+        };
+        while self.range < 128 {
+            self.value = (self.value << 1) | self.next_bit();
+            self.range <<= 1;
+        }
+        bit
+    }
+
+    /// Read an unsigned literal of `n` bits with even probability
+    pub fn read_literal(&mut self, n: u32) -> u32 {
+        (0..n).fold(0, |acc, _| (acc << 1) | self.read_bool(128) as u32)
+    }
 }
 
 /// VP9 Decoder
 #[derive(Debug)]
 pub struct Vp9Decoder {
-    dpb: DecodedPictureBuffer,
     state: VideoDecoderState,
     ref_frames: [Option<VideoFrame>; 8],
     pending_output: Vec<VideoFrame>,
     profile: u8,
+    /// Colour configuration carried over to inter frames
+    bit_depth: u8,
+    color_space: Vp9ColorSpace,
+    subsampling: (bool, bool),
 }
 
 impl Vp9Decoder {
     pub fn new() -> Self {
         Self {
-            dpb: DecodedPictureBuffer::new(8),
             state: VideoDecoderState::default(),
             ref_frames: Default::default(),
             pending_output: Vec::new(),
             profile: 0,
+            bit_depth: 8,
+            color_space: Vp9ColorSpace::Unknown,
+            subsampling: (true, true),
         }
     }
-    
+
     fn parse_superframe_index(&self, data: &[u8]) -> Vec<(usize, usize)> {
         let mut frames = Vec::new();
         if data.is_empty() { return frames; }
-        
+
         let marker = data[data.len() - 1];
-        if (marker & 0xE0) != 0xC0 { 
+        if (marker & 0xE0) != 0xC0 {
             frames.push((0, data.len()));
-            return frames; 
+            return frames;
         }
-        
+
         let bytes_per_framesize = ((marker >> 3) & 3) as usize + 1;
         let num_frames = (marker & 7) as usize + 1;
         let index_size = 2 + num_frames * bytes_per_framesize;
-        
-        if data.len() < index_size { 
+
+        if data.len() < index_size {
             frames.push((0, data.len()));
-            return frames; 
+            return frames;
         }
-        
+
         let idx_start = data.len() - index_size;
         if data[idx_start] != marker {
             frames.push((0, data.len()));
             return frames;
         }
-        
+
         let mut offset = 0;
         for i in 0..num_frames {
             let mut size = 0usize;
@@ -96,101 +193,257 @@ impl Vp9Decoder {
         }
         frames
     }
-    
-    fn parse_frame_header(&self, data: &[u8]) -> DecoderResult<Vp9FrameHeader> {
-        if data.len() < 3 { return Err(DecoderError::NeedMoreData); }
-        
-        let mut pos = 0;
-        let mut bit = 0;
-        
-        let read_bits = |pos: &mut usize, bit: &mut usize, n: usize| -> DecoderResult<u32> {
-            let mut val = 0u32;
-            for _ in 0..n {
-                if *pos >= data.len() { return Err(DecoderError::NeedMoreData); }
-                let b = (data[*pos] >> (7 - *bit)) & 1;
-                val = (val << 1) | b as u32;
-                *bit += 1;
-                if *bit == 8 { *bit = 0; *pos += 1; }
+
+    fn read_signed(r: &mut BitReader, bits: u8) -> DecoderResult<i32> {
+        let value = r.read_bits(bits)? as i32;
+        Ok(if r.read_bit()? { -value } else { value })
+    }
+
+    /// color_config(): bit depth, colour space and subsampling
+    fn read_color_config(&mut self, r: &mut BitReader, profile: u8) -> DecoderResult<()> {
+        self.bit_depth = if profile >= 2 { if r.read_bit()? { 12 } else { 10 } } else { 8 };
+        self.color_space = Vp9ColorSpace::from(r.read_bits(3)?);
+        if self.color_space != Vp9ColorSpace::Srgb {
+            r.skip(1)?; // color_range
+            self.subsampling = if profile == 1 || profile == 3 {
+                let ss = (r.read_bit()?, r.read_bit()?);
+                r.skip(1)?;
+                ss
+            } else {
+                (true, true)
+            };
+        } else {
+            if profile == 0 || profile == 2 {
+                return Err(DecoderError::InvalidBitstream("sRGB requires 4:4:4 profile".into()));
             }
-            Ok(val)
-        };
-        
-        let marker = read_bits(&mut pos, &mut bit, 2)?;
-        if marker != 2 { return Err(DecoderError::InvalidBitstream("Invalid VP9 marker".into())); }
-        
-        let profile_low = read_bits(&mut pos, &mut bit, 1)? as u8;
-        let profile_high = read_bits(&mut pos, &mut bit, 1)? as u8;
+            self.subsampling = (false, false);
+            r.skip(1)?;
+        }
+        Ok(())
+    }
+
+    fn read_frame_size(r: &mut BitReader) -> DecoderResult<(u32, u32)> {
+        Ok((r.read_bits(16)? + 1, r.read_bits(16)? + 1))
+    }
+
+    fn read_render_size(r: &mut BitReader, size: (u32, u32)) -> DecoderResult<(u32, u32)> {
+        if r.read_bit()? { Self::read_frame_size(r) } else { Ok(size) }
+    }
+
+    fn parse_frame_header(&mut self, data: &[u8]) -> DecoderResult<Vp9FrameHeader> {
+        if data.is_empty() { return Err(DecoderError::NeedMoreData); }
+        let mut r = BitReader::new(data);
+
+        if r.read_bits(2)? != 2 { return Err(DecoderError::InvalidBitstream("Invalid VP9 marker".into())); }
+        let profile_low = r.read_bits(1)? as u8;
+        let profile_high = r.read_bits(1)? as u8;
         let profile = (profile_high << 1) | profile_low;
-        
-        let show_existing = read_bits(&mut pos, &mut bit, 1)? == 1;
-        if show_existing {
-            let _idx = read_bits(&mut pos, &mut bit, 3)?;
-            return Ok(Vp9FrameHeader {
-                frame_type: Vp9FrameType::InterFrame, show_frame: true, error_resilient: false,
-                width: self.state.width, height: self.state.height,
-                render_width: self.state.width, render_height: self.state.height,
-                profile, bit_depth: 8, color_space: Vp9ColorSpace::Unknown,
-                subsampling_x: true, subsampling_y: true, refresh_frame_flags: 0,
-                ref_frame_idx: [0; 3], ref_frame_sign_bias: [false; 4],
-                allow_high_precision_mv: false, interp_filter: 0,
-            });
-        }
-        
-        let frame_type = if read_bits(&mut pos, &mut bit, 1)? == 0 { Vp9FrameType::KeyFrame } else { Vp9FrameType::InterFrame };
-        let show_frame = read_bits(&mut pos, &mut bit, 1)? == 1;
-        let error_resilient = read_bits(&mut pos, &mut bit, 1)? == 1;
-        
-        let (width, height, bit_depth) = if frame_type == Vp9FrameType::KeyFrame {
-            let _sync = read_bits(&mut pos, &mut bit, 24)?;
-            let bit_depth = if profile >= 2 { if read_bits(&mut pos, &mut bit, 1)? == 1 { 12 } else { 10 } } else { 8 };
-            let _cs = read_bits(&mut pos, &mut bit, 3)?;
-            if profile >= 1 { read_bits(&mut pos, &mut bit, 1)?; if profile >= 2 { read_bits(&mut pos, &mut bit, 1)?; } }
-            let w = read_bits(&mut pos, &mut bit, 16)? + 1;
-            let h = read_bits(&mut pos, &mut bit, 16)? + 1;
-            (w, h, bit_depth as u8)
+        if profile == 3 { r.skip(1)?; }
+
+        let mut header = Vp9FrameHeader {
+            frame_type: Vp9FrameType::InterFrame, show_frame: true, show_existing_frame: None,
+            error_resilient: false, intra_only: false,
+            width: self.state.width, height: self.state.height,
+            render_width: self.state.width, render_height: self.state.height,
+            profile, bit_depth: self.bit_depth, color_space: self.color_space,
+            subsampling_x: self.subsampling.0, subsampling_y: self.subsampling.1,
+            refresh_frame_flags: 0, ref_frame_idx: [0; 3], ref_frame_sign_bias: [false; 4],
+            allow_high_precision_mv: false, interp_filter: 0, refresh_frame_context: false,
+            frame_context_idx: 0, loop_filter_level: 0, sharpness: 0, base_q_idx: 0, lossless: false,
+            tile_cols_log2: 0, tile_rows_log2: 0, header_size: 0, uncompressed_size: 0,
+            tx_mode: Vp9TxMode::Only4x4,
+        };
+
+        if r.read_bit()? {
+            header.show_existing_frame = Some(r.read_bits(3)? as u8);
+            return Ok(header);
+        }
+
+        header.frame_type = if r.read_bit()? { Vp9FrameType::InterFrame } else { Vp9FrameType::KeyFrame };
+        header.show_frame = r.read_bit()?;
+        header.error_resilient = r.read_bit()?;
+
+        let size = if header.frame_type == Vp9FrameType::KeyFrame {
+            if r.read_bits(24)? != SYNC_CODE { return Err(DecoderError::InvalidBitstream("Invalid VP9 sync code".into())); }
+            self.read_color_config(&mut r, profile)?;
+            header.refresh_frame_flags = 0xFF;
+            Self::read_frame_size(&mut r)?
         } else {
-            (self.state.width, self.state.height, 8)
+            header.intra_only = if header.show_frame { false } else { r.read_bit()? };
+            if !header.error_resilient { r.skip(2)?; } // reset_frame_context
+            if header.intra_only {
+                if r.read_bits(24)? != SYNC_CODE { return Err(DecoderError::InvalidBitstream("Invalid VP9 sync code".into())); }
+                if profile > 0 {
+                    self.read_color_config(&mut r, profile)?;
+                } else {
+                    self.bit_depth = 8;
+                    self.color_space = Vp9ColorSpace::Bt601;
+                    self.subsampling = (true, true);
+                }
+                header.refresh_frame_flags = r.read_bits(8)? as u8;
+                Self::read_frame_size(&mut r)?
+            } else {
+                header.refresh_frame_flags = r.read_bits(8)? as u8;
+                for (i, idx) in header.ref_frame_idx.iter_mut().enumerate() {
+                    *idx = r.read_bits(3)? as u8;
+                    header.ref_frame_sign_bias[i + 1] = r.read_bit()?;
+                }
+                // frame_size_with_refs()
+                let mut found = None;
+                for &idx in &header.ref_frame_idx {
+                    if r.read_bit()? {
+                        let slot = self.ref_frames[idx as usize].as_ref()
+                            .ok_or_else(|| DecoderError::InvalidBitstream("Missing VP9 reference frame".into()))?;
+                        found = Some((slot.width, slot.height));
+                        break;
+                    }
+                }
+                let size = match found { Some(s) => s, None => Self::read_frame_size(&mut r)? };
+                let render = Self::read_render_size(&mut r, size)?;
+                header.render_width = render.0;
+                header.render_height = render.1;
+                header.allow_high_precision_mv = r.read_bit()?;
+                header.interp_filter = if r.read_bit()? { 4 } else { [1, 0, 2, 3][r.read_bits(2)? as usize] };
+                size
+            }
+        };
+        if header.frame_type == Vp9FrameType::KeyFrame || header.intra_only {
+            let render = Self::read_render_size(&mut r, size)?;
+            header.render_width = render.0;
+            header.render_height = render.1;
+        }
+        header.width = size.0;
+        header.height = size.1;
+        header.bit_depth = self.bit_depth;
+        header.color_space = self.color_space;
+        (header.subsampling_x, header.subsampling_y) = self.subsampling;
+
+        if !header.error_resilient {
+            header.refresh_frame_context = r.read_bit()?;
+            r.skip(1)?; // frame_parallel_decoding_mode
+        }
+        header.frame_context_idx = r.read_bits(2)? as u8;
+
+        // loop_filter_params()
+        header.loop_filter_level = r.read_bits(6)? as u8;
+        header.sharpness = r.read_bits(3)? as u8;
+        if r.read_bit()? && r.read_bit()? {
+            for _ in 0..6 {
+                if r.read_bit()? { Self::read_signed(&mut r, 6)?; }
+            }
+        }
+
+        // quantization_params()
+        header.base_q_idx = r.read_bits(8)? as u8;
+        let mut deltas_zero = true;
+        for _ in 0..3 {
+            if r.read_bit()? { deltas_zero &= Self::read_signed(&mut r, 4)? == 0; }
+        }
+        header.lossless = header.base_q_idx == 0 && deltas_zero;
+
+        // segmentation_params()
+        if r.read_bit()? {
+            if r.read_bit()? {
+                for _ in 0..7 { if r.read_bit()? { r.skip(8)?; } }
+                if r.read_bit()? {
+                    for _ in 0..3 { if r.read_bit()? { r.skip(8)?; } }
+                }
+            }
+            if r.read_bit()? {
+                r.skip(1)?; // segmentation_abs_or_delta_update
+                for _ in 0..8 {
+                    for (bits, signed) in [(8u8, true), (6, true), (2, false), (0, false)] {
+                        if r.read_bit()? {
+                            r.skip(bits as usize + signed as usize)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        // tile_info()
+        let sb64_cols = (size.0 as usize).div_ceil(64);
+        let mut min_log2 = 0;
+        while (64 << min_log2) < sb64_cols { min_log2 += 1; }
+        let mut max_log2 = 1;
+        while (sb64_cols >> max_log2) >= 4 { max_log2 += 1; }
+        max_log2 -= 1;
+        header.tile_cols_log2 = min_log2 as u8;
+        while header.tile_cols_log2 < max_log2 && r.read_bit()? { header.tile_cols_log2 += 1; }
+        header.tile_rows_log2 = r.read_bits(1)? as u8;
+        if header.tile_rows_log2 == 1 { header.tile_rows_log2 += r.read_bits(1)? as u8; }
+
+        header.header_size = r.read_bits(16)? as usize;
+        if header.header_size == 0 { return Err(DecoderError::InvalidBitstream("Empty VP9 compressed header".into())); }
+        r.byte_align();
+        header.uncompressed_size = data.len() - r.remaining_bytes();
+
+        // First syntax element of the compressed header
+        let end = header.uncompressed_size + header.header_size;
+        if end > data.len() { return Err(DecoderError::NeedMoreData); }
+        let mut bd = BoolDecoder::new(&data[header.uncompressed_size..end])?;
+        header.tx_mode = if header.lossless {
+            Vp9TxMode::Only4x4
+        } else {
+            match bd.read_literal(2) {
+                0 => Vp9TxMode::Only4x4,
+                1 => Vp9TxMode::Allow8x8,
+                2 => Vp9TxMode::Allow16x16,
+                _ if bd.read_literal(1) == 1 => Vp9TxMode::TxModeSelect,
+                _ => Vp9TxMode::Allow32x32,
+            }
         };
-        
-        Ok(Vp9FrameHeader {
-            frame_type, show_frame, error_resilient, width, height,
-            render_width: width, render_height: height, profile, bit_depth,
-            color_space: Vp9ColorSpace::Unknown, subsampling_x: true, subsampling_y: true,
-            refresh_frame_flags: 0xFF, ref_frame_idx: [0; 3], ref_frame_sign_bias: [false; 4],
-            allow_high_precision_mv: false, interp_filter: 0,
-        })
-    }
-    
+        Ok(header)
+    }
+
+    fn blank_frame(w: u32, h: u32, format: PixelFormat) -> VideoFrame {
+        let (cw, ch) = (w.div_ceil(2) as usize, h.div_ceil(2) as usize);
+        VideoFrame {
+            pts: Duration::ZERO, dts: Duration::ZERO, duration: Duration::from_millis(33), width: w, height: h,
+            format, key_frame: false,
+            planes: vec![
+                Plane { data: vec![128; (w * h) as usize], stride: w as usize },
+                Plane { data: vec![128; cw * ch], stride: cw },
+                Plane { data: vec![128; cw * ch], stride: cw },
+            ],
+            surface: None,
+        }
+    }
+
     fn decode_frame(&mut self, data: &[u8], pts: Duration, dts: Duration) -> DecoderResult<Option<VideoFrame>> {
         let header = self.parse_frame_header(data)?;
-        
+        self.profile = header.profile;
+
+        if let Some(slot) = header.show_existing_frame {
+            let frame = self.ref_frames[slot as usize].as_ref()
+                .ok_or_else(|| DecoderError::InvalidBitstream("show_existing_frame of empty slot".into()))?;
+            return Ok(Some(VideoFrame { pts, dts, ..frame.clone() }));
+        }
+
         self.state.width = header.width;
         self.state.height = header.height;
-        self.profile = header.profile;
-        
         let (w, h) = (header.width, header.height);
-        let y_size = (w * h) as usize;
-        let uv_size = y_size / 4;
-        
         let format = if header.bit_depth > 8 { PixelFormat::I420_10 } else { PixelFormat::I420 };
-        
-        let frame = VideoFrame {
-            pts, dts, duration: Duration::from_millis(33), width: w, height: h,
-            format, key_frame: header.frame_type == Vp9FrameType::KeyFrame,
-            planes: vec![
-                Plane { data: vec![128; y_size], stride: w as usize },
-                Plane { data: vec![128; uv_size], stride: (w/2) as usize },
-                Plane { data: vec![128; uv_size], stride: (w/2) as usize },
-            ],
+        self.state.format = format;
+
+        let is_intra = header.frame_type == Vp9FrameType::KeyFrame || header.intra_only;
+        let last = if is_intra { None } else { self.ref_frames[header.ref_frame_idx[0] as usize].as_ref() };
+        let mut frame = match last {
+            Some(f) if f.width == w && f.height == h => f.clone(),
+            _ => Self::blank_frame(w, h, format),
         };
-        
+        frame.pts = pts;
+        frame.dts = dts;
+        frame.key_frame = header.frame_type == Vp9FrameType::KeyFrame;
+        self.state.frame_num += 1;
+
         // Update reference frames
-        for i in 0..8 {
+        for (i, slot) in self.ref_frames.iter_mut().enumerate() {
             if (header.refresh_frame_flags >> i) & 1 == 1 {
-                self.ref_frames[i] = Some(frame.clone());
+                *slot = Some(frame.clone());
             }
         }
-        
+
         if header.show_frame { Ok(Some(frame)) } else { Ok(None) }
     }
 }
@@ -210,13 +463,74 @@ impl VideoDecoderTrait for Vp9Decoder {
         Ok(output)
     }
     fn flush(&mut self) -> Vec<VideoFrame> { std::mem::take(&mut self.pending_output) }
-    fn reset(&mut self) { self.ref_frames = Default::default(); self.state = VideoDecoderState::default(); }
+    fn reset(&mut self) {
+        self.ref_frames = Default::default();
+        self.pending_output.clear();
+        self.state = VideoDecoderState::default();
+    }
     fn capabilities(&self) -> DecoderCaps { DecoderCaps { max_width: 8192, max_height: 4320, formats: vec![PixelFormat::I420, PixelFormat::I420_10], hardware: false } }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    /// Uncompressed header of a 64x48 profile 0 key frame followed by a
+    /// one-byte compressed header
+    fn key_frame() -> Vec<u8> {
+        let fields = [
+            "10".to_string(), // frame_marker
+            "00".to_string(), // profile 0
+            "0".to_string(), // show_existing_frame
+            "0".to_string(), // key frame
+            "1".to_string(), // show_frame
+            "0".to_string(), // error_resilient_mode
+            format!("{:024b}", SYNC_CODE),
+            "010".to_string(), // BT.709
+            "0".to_string(), // color_range
+            format!("{:016b}{:016b}", 63, 47),
+            "0".to_string(), // render_and_frame_size_different
+            "00".to_string(), // refresh_frame_context, frame_parallel_decoding_mode
+            "00".to_string(), // frame_context_idx
+            format!("{:06b}{:03b}0", 10, 0), // loop filter
+            format!("{:08b}000", 60), // quantizer
+            "0".to_string(), // segmentation
+            "0".to_string(), // tile_rows_log2
+            format!("{:016b}", 1), // header_size_in_bytes
+        ];
+        let mut bits = fields.concat();
+        while bits.len() % 8 != 0 { bits.push('0'); }
+        let mut out: Vec<u8> = bits.as_bytes().chunks(8)
+            .map(|c| u8::from_str_radix(std::str::from_utf8(c).unwrap(), 2).unwrap())
+            .collect();
+        out.push(0x00);
+        out
+    }
+
     #[test]
     fn test_decoder() { let d = Vp9Decoder::new(); assert_eq!(d.capabilities().max_width, 8192); }
+
+    #[test]
+    fn test_key_frame_header() {
+        let mut d = Vp9Decoder::new();
+        let header = d.parse_frame_header(&key_frame()).unwrap();
+        assert_eq!((header.width, header.height), (64, 48));
+        assert_eq!(header.color_space, Vp9ColorSpace::Bt709);
+        assert_eq!(header.refresh_frame_flags, 0xFF);
+        assert_eq!(header.base_q_idx, 60);
+        assert_eq!(header.header_size, 1);
+        assert_eq!(header.tx_mode, Vp9TxMode::Only4x4);
+    }
+
+    #[test]
+    fn test_show_existing_frame() {
+        let mut d = Vp9Decoder::new();
+        let packet = EncodedPacket { data: key_frame(), pts: Duration::ZERO, dts: Duration::ZERO, is_key: true };
+        assert_eq!(d.decode(&packet).unwrap().len(), 1);
+        // show_existing_frame of slot 3
+        let packet = EncodedPacket { data: vec![0b1000_1011], pts: Duration::from_millis(40), dts: Duration::from_millis(40), is_key: false };
+        let frames = d.decode(&packet).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!((frames[0].width, frames[0].height, frames[0].pts), (64, 48, Duration::from_millis(40)));
+    }
 }
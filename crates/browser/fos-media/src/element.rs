@@ -4,6 +4,12 @@
 
 use std::time::Duration;
 
+use crate::pipeline::MediaPipeline;
+use crate::pipeline::clock::{MAX_PLAYBACK_RATE, MIN_PLAYBACK_RATE};
+use crate::tracks::{TextTrack, TextTrackEvent, TextTrackList};
+use crate::vtt::layout::{layout_cues, CueBox, TextMeasure};
+use crate::vtt::VttCue;
+
 /// Network state
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum NetworkState {
@@ -101,10 +107,17 @@ pub struct HTMLMediaElement {
     // Playback rate
     pub playback_rate: f64,
     pub default_playback_rate: f64,
+    /// Keep audio pitch when the rate is not 1.0
+    pub preserves_pitch: bool,
     
     // Controls
     pub controls: bool,
     pub preload: PreloadHint,
+    
+    // Text tracks
+    pub text_tracks: TextTrackList,
+    /// A seek happened since the last text track update
+    tracks_seeked: bool,
 }
 
 /// Preload hint
@@ -140,8 +153,11 @@ impl HTMLMediaElement {
             played: TimeRanges::new(),
             playback_rate: 1.0,
             default_playback_rate: 1.0,
+            preserves_pitch: true,
             controls: false,
             preload: PreloadHint::Metadata,
+            text_tracks: TextTrackList::new(),
+            tracks_seeked: false,
         }
     }
     
@@ -177,14 +193,23 @@ impl HTMLMediaElement {
         self.seeking = true;
         self.current_time = time.clamp(0.0, self.duration);
         self.seeking = false;
+        self.tracks_seeked = true;
     }
     
     /// Check if can play type
     pub fn can_play_type(&self, mime_type: &str) -> CanPlayType {
-        match mime_type {
-            "video/mp4" | "video/webm" | "audio/mp3" | "audio/mpeg" 
-            | "audio/ogg" | "audio/wav" => CanPlayType::Probably,
-            "video/ogg" | "audio/aac" => CanPlayType::Maybe,
+        let (essence, _) = crate::containers::parse_mime(mime_type);
+        if crate::containers::format_for_mime(&essence).is_some() {
+            // Container types: answer from the demuxers and codec registry
+            return match crate::containers::type_support(mime_type) {
+                crate::codecs::CodecSupport::Supported => CanPlayType::Probably,
+                crate::codecs::CodecSupport::MaybeSupported => CanPlayType::Maybe,
+                crate::codecs::CodecSupport::Unsupported => CanPlayType::Empty,
+            };
+        }
+        match essence.as_str() {
+            "audio/mp3" | "audio/mpeg" | "audio/ogg" | "audio/wav"
+            | "video/ogg" | "audio/aac" => CanPlayType::Maybe,
             _ => CanPlayType::Empty,
         }
     }
@@ -192,6 +217,62 @@ impl HTMLMediaElement {
     /// Fast seek
     pub fn fast_seek(&mut self, time: f64) {
         self.current_time = time.clamp(0.0, self.duration);
+        self.tracks_seeked = true;
+    }
+    
+    /// Hand `playbackRate` and `preservesPitch` to the pipeline and take
+    /// `currentTime` from its clock; returns whether the rate changed
+    ///
+    /// The pipeline clamps the rate to what it can play; the attribute keeps
+    /// the value the page set.
+    pub fn sync_pipeline(&mut self, pipeline: &mut MediaPipeline) -> bool {
+        if pipeline.preserves_pitch() != self.preserves_pitch {
+            pipeline.set_preserves_pitch(self.preserves_pitch);
+        }
+        let rate = self.playback_rate.clamp(MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE);
+        let rate_changed = pipeline.playback_rate() != rate;
+        if rate_changed {
+            pipeline.set_playback_rate(rate);
+        }
+        if !self.seeking {
+            self.current_time = pipeline.position().as_secs_f64();
+        }
+        rate_changed
+    }
+    
+    /// Add a text track, returning its index
+    pub fn add_text_track(&mut self, track: TextTrack) -> usize {
+        self.text_tracks.add(track)
+    }
+    
+    /// Run "time marches on" for every text track at the current time
+    ///
+    /// Returns `(track index, event)` pairs. Leaving a `pauseOnExit` cue during
+    /// normal playback pauses the element.
+    pub fn update_text_tracks(&mut self) -> Vec<(usize, TextTrackEvent)> {
+        let seeking = std::mem::take(&mut self.tracks_seeked);
+        let mut events = Vec::new();
+        let mut pause = false;
+        for (index, track) in self.text_tracks.tracks.iter_mut().enumerate() {
+            for event in track.time_marches_on(self.current_time, seeking) {
+                if let TextTrackEvent::Exit(cue) = event {
+                    pause |= !seeking && track.cues.get(cue).is_some_and(|c| c.pause_on_exit);
+                }
+                events.push((index, event));
+            }
+        }
+        if pause {
+            self.pause();
+        }
+        events
+    }
+    
+    /// Lay out the active cues of showing tracks over a `width` x `height` video
+    pub fn cue_boxes(&self, width: f32, height: f32, measure: &mut dyn TextMeasure) -> Vec<CueBox> {
+        let cues: Vec<VttCue> = self.text_tracks.showing().flat_map(TextTrack::active_vtt_cues).collect();
+        let regions: Vec<_> = self.text_tracks.showing().flat_map(|t| t.regions.iter().cloned()).collect();
+        let refs: Vec<&VttCue> = cues.iter().collect();
+        layout_cues(&refs, &regions, width, height, self.current_time, measure)
     }
 }
 
@@ -296,7 +377,37 @@ mod tests {
     #[test]
     fn test_can_play_type() {
         let media = HTMLMediaElement::new();
-        assert_eq!(media.can_play_type("video/mp4"), CanPlayType::Probably);
+        assert_eq!(media.can_play_type("video/mp4"), CanPlayType::Maybe);
+        assert_eq!(media.can_play_type("video/webm; codecs=\"av01.0.05M.08, opus\""), CanPlayType::Probably);
+        assert_eq!(media.can_play_type("video/webm; codecs=\"vp8, opus\""), CanPlayType::Empty);
+        assert_eq!(media.can_play_type("video/webm; codecs=\"vp9, opus\""), CanPlayType::Empty);
+        assert_eq!(media.can_play_type("video/webm; codecs=\"hvc1\""), CanPlayType::Empty);
         assert_eq!(media.can_play_type("video/unknown"), CanPlayType::Empty);
     }
+    
+    #[test]
+    fn test_text_track_cues() {
+        use crate::tracks::{TextTrackKind, TextTrackMode};
+        use crate::vtt::layout::ApproxMeasure;
+        
+        let mut media = HTMLMediaElement::new();
+        media.duration = 10.0;
+        let mut track = TextTrack::new(TextTrackKind::Captions, "English", "en");
+        track.load_vtt("WEBVTT\n\n00:01.000 --> 00:02.000\nHello\n").unwrap();
+        track.cues[0].pause_on_exit = true;
+        track.mode = TextTrackMode::Showing;
+        media.add_text_track(track);
+        media.paused = false;
+        
+        media.current_time = 1.5;
+        assert_eq!(media.update_text_tracks().len(), 2);
+        let boxes = media.cue_boxes(640.0, 360.0, &mut ApproxMeasure);
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes[0].lines[0].runs[0].text, "Hello");
+        
+        media.current_time = 2.5;
+        media.update_text_tracks();
+        assert!(media.paused);
+        assert!(media.cue_boxes(640.0, 360.0, &mut ApproxMeasure).is_empty());
+    }
 }
@@ -0,0 +1,177 @@
+//! AES-128
+//!
+//! Forward block cipher and CTR mode for CENC sample decryption. CTR decrypts
+//! by encrypting the counter, so the inverse cipher is not needed.
+
+/// S-box, computed at compile time from the GF(2^8) inverse and affine map
+const SBOX: [u8; 256] = build_sbox();
+const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1B, 0x36];
+
+const fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 { product ^= a; }
+        a = xtime(a);
+        b >>= 1;
+    }
+    product
+}
+
+const fn build_sbox() -> [u8; 256] {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        // Inverse as x^254
+        let mut inverse = 1u8;
+        let mut n = 0;
+        while n < 254 && i != 0 {
+            inverse = gf_mul(inverse, i as u8);
+            n += 1;
+        }
+        if i == 0 { inverse = 0; }
+        table[i] = inverse ^ inverse.rotate_left(1) ^ inverse.rotate_left(2)
+            ^ inverse.rotate_left(3) ^ inverse.rotate_left(4) ^ 0x63;
+        i += 1;
+    }
+    table
+}
+
+const fn xtime(b: u8) -> u8 {
+    (b << 1) ^ if b & 0x80 != 0 { 0x1B } else { 0 }
+}
+
+/// AES-128 encryption with an expanded key
+#[derive(Clone)]
+pub struct Aes128 {
+    round_keys: [[u8; 16]; 11],
+}
+
+impl std::fmt::Debug for Aes128 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Aes128 { .. }")
+    }
+}
+
+impl Aes128 {
+    pub fn new(key: &[u8; 16]) -> Self {
+        let mut words = [[0u8; 4]; 44];
+        for (i, word) in words.iter_mut().take(4).enumerate() {
+            word.copy_from_slice(&key[i * 4..i * 4 + 4]);
+        }
+        for i in 4..44 {
+            let mut temp = words[i - 1];
+            if i % 4 == 0 {
+                temp = [SBOX[temp[1] as usize] ^ RCON[i / 4 - 1], SBOX[temp[2] as usize], SBOX[temp[3] as usize], SBOX[temp[0] as usize]];
+            }
+            for b in 0..4 {
+                words[i][b] = words[i - 4][b] ^ temp[b];
+            }
+        }
+        let mut round_keys = [[0u8; 16]; 11];
+        for (round, key) in round_keys.iter_mut().enumerate() {
+            for w in 0..4 {
+                key[w * 4..w * 4 + 4].copy_from_slice(&words[round * 4 + w]);
+            }
+        }
+        Self { round_keys }
+    }
+
+    /// Encrypt one block in place
+    pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+        add_round_key(block, &self.round_keys[0]);
+        for round in 1..11 {
+            // State is column-major: byte `c * 4 + r` is row r of column c
+            let mut shifted = [0u8; 16];
+            for c in 0..4 {
+                for r in 0..4 {
+                    shifted[c * 4 + r] = SBOX[block[((c + r) % 4) * 4 + r] as usize];
+                }
+            }
+            if round < 10 {
+                for column in shifted.chunks_exact_mut(4) {
+                    let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+                    let t = a0 ^ a1 ^ a2 ^ a3;
+                    column[0] = a0 ^ t ^ xtime(a0 ^ a1);
+                    column[1] = a1 ^ t ^ xtime(a1 ^ a2);
+                    column[2] = a2 ^ t ^ xtime(a2 ^ a3);
+                    column[3] = a3 ^ t ^ xtime(a3 ^ a0);
+                }
+            }
+            *block = shifted;
+            add_round_key(block, &self.round_keys[round]);
+        }
+    }
+}
+
+fn add_round_key(block: &mut [u8; 16], key: &[u8; 16]) {
+    for (b, k) in block.iter_mut().zip(key) {
+        *b ^= k;
+    }
+}
+
+/// AES-128 CTR keystream; the counter is the IV as a 128-bit big-endian
+/// integer (8-byte IVs occupy the high half)
+#[derive(Debug, Clone)]
+pub struct Ctr {
+    cipher: Aes128,
+    counter: [u8; 16],
+    keystream: [u8; 16],
+    /// Keystream bytes of the current block already used
+    used: usize,
+}
+
+impl Ctr {
+    pub fn new(key: &[u8; 16], iv: &[u8]) -> Self {
+        let mut counter = [0u8; 16];
+        let n = iv.len().min(16);
+        counter[..n].copy_from_slice(&iv[..n]);
+        Self { cipher: Aes128::new(key), counter, keystream: [0; 16], used: 16 }
+    }
+
+    /// XOR `data` with the keystream, continuing where the last call stopped
+    pub fn apply(&mut self, data: &mut [u8]) {
+        for byte in data {
+            if self.used == 16 {
+                self.keystream = self.counter;
+                self.cipher.encrypt_block(&mut self.keystream);
+                for b in self.counter.iter_mut().rev() {
+                    *b = b.wrapping_add(1);
+                    if *b != 0 { break; }
+                }
+                self.used = 0;
+            }
+            *byte ^= self.keystream[self.used];
+            self.used += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_fips197_vector() {
+        let key: [u8; 16] = hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap();
+        let mut block: [u8; 16] = hex("00112233445566778899aabbccddeeff").try_into().unwrap();
+        Aes128::new(&key).encrypt_block(&mut block);
+        assert_eq!(block.to_vec(), hex("69c4e0d86a7b0430d8cdb78070b4c55a"));
+    }
+
+    #[test]
+    fn test_ctr_sp800_38a() {
+        // NIST SP 800-38A F.5.1, split unevenly to exercise keystream carry-over
+        let key: [u8; 16] = hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap();
+        let iv = hex("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let mut data = hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+        let mut ctr = Ctr::new(&key, &iv);
+        let (a, b) = data.split_at_mut(5);
+        ctr.apply(a);
+        ctr.apply(b);
+        assert_eq!(data, hex("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Common Encryption (CENC)
+//!
+//! `pssh` init data parsing and `cenc` scheme sample decryption: AES-128 CTR
+//! over the encrypted byte ranges of each subsample, as one continuous
+//! keystream. Pattern schemes (`cens`, `cbcs`) are not supported.
+
+use super::aes::Ctr;
+use super::EmeError;
+use crate::containers::SampleEncryption;
+use crate::containers::mp4::read_u32;
+
+/// W3C Common PSSH system ID (1077efec-c0b2-4d02-ace3-3c1e52e2fb4b)
+pub const COMMON_SYSTEM_ID: [u8; 16] = [
+    0x10, 0x77, 0xEF, 0xEC, 0xC0, 0xB2, 0x4D, 0x02, 0xAC, 0xE3, 0x3C, 0x1E, 0x52, 0xE2, 0xFB, 0x4B,
+];
+
+/// Parsed `pssh` box
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PsshBox {
+    pub system_id: [u8; 16],
+    /// Key IDs (version 1 boxes only)
+    pub key_ids: Vec<[u8; 16]>,
+    /// System-specific data
+    pub data: Vec<u8>,
+}
+
+/// Parse concatenated `pssh` boxes (`cenc` init data)
+pub fn parse_pssh(init_data: &[u8]) -> Result<Vec<PsshBox>, EmeError> {
+    let mut boxes = Vec::new();
+    let mut pos = 0;
+    while pos < init_data.len() {
+        let size = init_data.get(pos..pos + 4).map(|_| read_u32(init_data, pos) as usize).ok_or(EmeError::InvalidData)?;
+        let end = pos.checked_add(size).filter(|&e| size >= 32 && e <= init_data.len()).ok_or(EmeError::InvalidData)?;
+        if &init_data[pos + 4..pos + 8] != b"pssh" {
+            return Err(EmeError::InvalidData);
+        }
+        let version = init_data[pos + 8];
+        let mut system_id = [0u8; 16];
+        system_id.copy_from_slice(&init_data[pos + 12..pos + 28]);
+        let mut p = pos + 28;
+        let mut key_ids = Vec::new();
+        if version > 0 {
+            let count = init_data.get(p..p + 4).map(|_| read_u32(init_data, p) as usize).ok_or(EmeError::InvalidData)?;
+            p += 4;
+            if count > (end - p) / 16 {
+                return Err(EmeError::InvalidData);
+            }
+            for _ in 0..count {
+                let mut kid = [0u8; 16];
+                kid.copy_from_slice(&init_data[p..p + 16]);
+                key_ids.push(kid);
+                p += 16;
+            }
+        }
+        let data_len = init_data.get(p..p + 4).map(|_| read_u32(init_data, p) as usize).ok_or(EmeError::InvalidData)?;
+        let data = init_data.get(p + 4..p + 4 + data_len).filter(|_| p + 4 + data_len <= end).ok_or(EmeError::InvalidData)?;
+        boxes.push(PsshBox { system_id, key_ids, data: data.to_vec() });
+        pos = end;
+    }
+    Ok(boxes)
+}
+
+/// Key IDs a ClearKey session should request: those of Common PSSH boxes
+pub fn key_ids(init_data: &[u8]) -> Result<Vec<[u8; 16]>, EmeError> {
+    let mut ids: Vec<[u8; 16]> = Vec::new();
+    for pssh in parse_pssh(init_data)? {
+        if pssh.system_id != COMMON_SYSTEM_ID { continue; }
+        for kid in pssh.key_ids {
+            if !ids.contains(&kid) { ids.push(kid); }
+        }
+    }
+    Ok(ids)
+}
+
+/// Decrypt a `cenc` sample in place
+pub fn decrypt_sample(key: &[u8; 16], encryption: &SampleEncryption, data: &mut [u8]) -> Result<(), EmeError> {
+    if &encryption.scheme != b"cenc" {
+        return Err(EmeError::NotSupported);
+    }
+    if encryption.iv.len() != 8 && encryption.iv.len() != 16 {
+        return Err(EmeError::InvalidData);
+    }
+    let mut ctr = Ctr::new(key, &encryption.iv);
+    if encryption.subsamples.is_empty() {
+        ctr.apply(data);
+        return Ok(());
+    }
+    let mut pos = 0usize;
+    for subsample in &encryption.subsamples {
+        let start = pos + subsample.clear_bytes as usize;
+        let end = start + subsample.encrypted_bytes as usize;
+        let range = data.get_mut(start..end).ok_or(EmeError::InvalidData)?;
+        ctr.apply(range);
+        pos = end;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::containers::Subsample;
+
+    pub(crate) fn pssh_v1(kids: &[[u8; 16]]) -> Vec<u8> {
+        let mut body = vec![1, 0, 0, 0];
+        body.extend_from_slice(&COMMON_SYSTEM_ID);
+        body.extend_from_slice(&(kids.len() as u32).to_be_bytes());
+        for kid in kids { body.extend_from_slice(kid); }
+        body.extend_from_slice(&0u32.to_be_bytes());
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(b"pssh");
+        out.extend(body);
+        out
+    }
+
+    #[test]
+    fn test_pssh_key_ids() {
+        let init = [pssh_v1(&[[1; 16], [2; 16]]), pssh_v1(&[[2; 16]])].concat();
+        assert_eq!(key_ids(&init).unwrap(), vec![[1; 16], [2; 16]]);
+        assert!(parse_pssh(&init[..40]).is_err());
+    }
+
+    #[test]
+    fn test_subsamples_share_keystream() {
+        let key = [7u8; 16];
+        let plain: Vec<u8> = (0..40).collect();
+        let mut whole: Vec<u8> = plain[4..20].iter().chain(&plain[30..40]).copied().collect();
+        Ctr::new(&key, &[9; 8]).apply(&mut whole);
+
+        let mut sample = plain.clone();
+        sample[4..20].copy_from_slice(&whole[..16]);
+        sample[30..40].copy_from_slice(&whole[16..]);
+        let encryption = SampleEncryption {
+            scheme: *b"cenc",
+            key_id: [0; 16],
+            iv: vec![9; 8],
+            subsamples: vec![
+                Subsample { clear_bytes: 4, encrypted_bytes: 16 },
+                Subsample { clear_bytes: 10, encrypted_bytes: 10 },
+            ],
+        };
+        decrypt_sample(&key, &encryption, &mut sample).unwrap();
+        assert_eq!(sample, plain);
+    }
+}
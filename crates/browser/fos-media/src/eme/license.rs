@@ -0,0 +1,268 @@
+//! ClearKey License Format
+//!
+//! JSON messages exchanged by `org.w3.clearkey` sessions: the license request
+//! (`{"kids":[...],"type":"temporary"}`), the JWK Set license response and
+//! `keyids` init data. Key IDs and keys are unpadded base64url.
+
+use super::{EmeError, MediaKeySessionType};
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+pub fn base64url_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..=chunk.len() {
+            out.push(BASE64URL[(n >> (18 - i * 6) & 0x3F) as usize] as char);
+        }
+    }
+    out
+}
+
+/// Decode base64url, tolerating padding and the standard alphabet
+pub fn base64url_decode(text: &str) -> Result<Vec<u8>, EmeError> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in text.trim_end_matches('=').bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' | b'+' => 62,
+            b'_' | b'/' => 63,
+            _ => return Err(EmeError::InvalidData),
+        };
+        acc = acc << 6 | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// JSON value; only what ClearKey messages need
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self { Json::Str(s) => Some(s), _ => None }
+    }
+
+    fn as_array(&self) -> Option<&[Json]> {
+        match self { Json::Array(items) => Some(items), _ => None }
+    }
+}
+
+struct JsonParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn parse(input: &[u8]) -> Result<Json, EmeError> {
+        let mut parser = JsonParser { input, pos: 0 };
+        let value = parser.value(0)?;
+        parser.skip_ws();
+        if parser.pos != input.len() {
+            return Err(EmeError::InvalidData);
+        }
+        Ok(value)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.input.get(self.pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_ws();
+        if self.input.get(self.pos) == Some(&c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, EmeError> {
+        if depth > 16 {
+            return Err(EmeError::InvalidData);
+        }
+        self.skip_ws();
+        let rest = &self.input[self.pos.min(self.input.len())..];
+        for (word, value) in [("null", Json::Null), ("true", Json::Bool(true)), ("false", Json::Bool(false))] {
+            if rest.starts_with(word.as_bytes()) {
+                self.pos += word.len();
+                return Ok(value);
+            }
+        }
+        match rest.first() {
+            Some(b'"') => self.string().map(Json::Str),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        items.push(self.value(depth + 1)?);
+                        if self.eat(b']') { break; }
+                        if !self.eat(b',') { return Err(EmeError::InvalidData); }
+                    }
+                }
+                Ok(Json::Array(items))
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_ws();
+                        let key = self.string()?;
+                        if !self.eat(b':') { return Err(EmeError::InvalidData); }
+                        members.push((key, self.value(depth + 1)?));
+                        if self.eat(b'}') { break; }
+                        if !self.eat(b',') { return Err(EmeError::InvalidData); }
+                    }
+                }
+                Ok(Json::Object(members))
+            }
+            Some(c) if *c == b'-' || c.is_ascii_digit() => {
+                let len = rest.iter().take_while(|c| matches!(c, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')).count();
+                let text = std::str::from_utf8(&rest[..len]).map_err(|_| EmeError::InvalidData)?;
+                self.pos += len;
+                text.parse().map(Json::Number).map_err(|_| EmeError::InvalidData)
+            }
+            _ => Err(EmeError::InvalidData),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, EmeError> {
+        if self.input.get(self.pos) != Some(&b'"') {
+            return Err(EmeError::InvalidData);
+        }
+        self.pos += 1;
+        let mut out = Vec::new();
+        loop {
+            let c = *self.input.get(self.pos).ok_or(EmeError::InvalidData)?;
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = *self.input.get(self.pos).ok_or(EmeError::InvalidData)?;
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => out.push(b'\n'),
+                        b't' => out.push(b'\t'),
+                        b'r' => out.push(b'\r'),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0C),
+                        b'u' => {
+                            let hex = self.input.get(self.pos..self.pos + 4).ok_or(EmeError::InvalidData)?;
+                            let code = std::str::from_utf8(hex).ok()
+                                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                                .ok_or(EmeError::InvalidData)?;
+                            self.pos += 4;
+                            let ch = char::from_u32(code).unwrap_or('\u{FFFD}');
+                            out.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        other => out.push(other),
+                    }
+                }
+                _ => out.push(c),
+            }
+        }
+        String::from_utf8(out).map_err(|_| EmeError::InvalidData)
+    }
+}
+
+/// License request message for the given key IDs
+pub fn license_request(key_ids: &[Vec<u8>], session_type: MediaKeySessionType) -> Vec<u8> {
+    let kids: Vec<String> = key_ids.iter().map(|k| format!("\"{}\"", base64url_encode(k))).collect();
+    let kind = match session_type {
+        MediaKeySessionType::Temporary => "temporary",
+        MediaKeySessionType::PersistentLicense => "persistent-license",
+    };
+    format!("{{\"kids\":[{}],\"type\":\"{}\"}}", kids.join(","), kind).into_bytes()
+}
+
+/// Key IDs from `keyids` init data (`{"kids":[...]}`)
+pub fn parse_key_ids(init_data: &[u8]) -> Result<Vec<Vec<u8>>, EmeError> {
+    let json = JsonParser::parse(init_data)?;
+    let kids = json.get("kids").and_then(Json::as_array).ok_or(EmeError::InvalidData)?;
+    kids.iter()
+        .map(|k| k.as_str().ok_or(EmeError::InvalidData).and_then(base64url_decode))
+        .collect()
+}
+
+/// Symmetric key from a JWK Set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonWebKey {
+    pub key_id: Vec<u8>,
+    pub key: [u8; 16],
+}
+
+/// `oct` keys from a JWK Set license
+pub fn parse_jwk_set(response: &[u8]) -> Result<Vec<JsonWebKey>, EmeError> {
+    let json = JsonParser::parse(response)?;
+    let keys = json.get("keys").and_then(Json::as_array).ok_or(EmeError::InvalidData)?;
+    let mut out = Vec::new();
+    for jwk in keys {
+        if jwk.get("kty").and_then(Json::as_str) != Some("oct") {
+            continue;
+        }
+        let kid = jwk.get("kid").and_then(Json::as_str).ok_or(EmeError::InvalidData).and_then(base64url_decode)?;
+        let key = jwk.get("k").and_then(Json::as_str).ok_or(EmeError::InvalidData).and_then(base64url_decode)?;
+        let key: [u8; 16] = key.try_into().map_err(|_| EmeError::InvalidData)?;
+        out.push(JsonWebKey { key_id: kid, key });
+    }
+    if out.is_empty() {
+        return Err(EmeError::InvalidData);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64url() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"\xfb\xff\xfe"] {
+            assert_eq!(base64url_decode(&base64url_encode(data)).unwrap(), data);
+        }
+        assert_eq!(base64url_encode(b"\xfb\xff"), "-_8");
+        assert_eq!(base64url_decode("Zm9v").unwrap(), b"foo");
+    }
+
+    #[test]
+    fn test_license_messages() {
+        let request = license_request(&[vec![0xFF; 16]], MediaKeySessionType::Temporary);
+        assert_eq!(std::str::from_utf8(&request).unwrap(), r#"{"kids":["_____________________w"],"type":"temporary"}"#);
+        assert_eq!(parse_key_ids(&request).unwrap(), vec![vec![0xFF; 16]]);
+
+        let license = br#"{"keys":[{"kty":"oct","kid":"AAECAwQFBgcICQoLDA0ODw","k":"EBESExQVFhcYGRobHB0eHw"}],"type":"temporary"}"#;
+        let keys = parse_jwk_set(license).unwrap();
+        assert_eq!(keys[0].key_id, (0..16).collect::<Vec<u8>>());
+        assert_eq!(keys[0].key.to_vec(), (16..32).collect::<Vec<u8>>());
+        assert!(parse_jwk_set(br#"{"keys":[]}"#).is_err());
+        assert!(parse_jwk_set(b"{\"keys\":[").is_err());
+    }
+}
@@ -1,8 +1,17 @@
 //! Encrypted Media Extensions (EME)
 //!
-//! DRM support with Clear Key and CDM integration.
+//! DRM support with Clear Key and CDM integration. ClearKey sessions run
+//! the full flow: `generateRequest` from `cenc`/`keyids`/`webm` init data,
+//! `update` with a JWK Set license, key status changes, and CENC sample
+//! decryption in the media pipeline.
+
+pub mod aes;
+pub mod cenc;
+pub mod license;
 
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use crate::containers::SampleEncryption;
 
 /// Key system
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -81,6 +90,9 @@ impl Default for MediaKeySessionType {
     }
 }
 
+/// Media keys shared between script and the pipelines they are attached to
+pub type SharedMediaKeys = Arc<Mutex<MediaKeys>>;
+
 /// Media keys
 #[derive(Debug)]
 pub struct MediaKeys {
@@ -106,12 +118,51 @@ impl MediaKeys {
             key_statuses: HashMap::new(),
             expiration: f64::NAN,
             closed: false,
+            keys: ClearKey::new(),
+            request_generated: false,
+            events: Vec::new(),
         };
         self.next_session_id += 1;
         self.sessions.push(session);
         self.sessions.last_mut().unwrap()
     }
     
+    pub fn sessions(&self) -> &[MediaKeySession] { &self.sessions }
+    
+    pub fn session_mut(&mut self, session_id: &str) -> Option<&mut MediaKeySession> {
+        self.sessions.iter_mut().find(|s| s.session_id == session_id)
+    }
+    
+    /// Status of a key across all sessions, preferring a usable one
+    pub fn key_status(&self, key_id: &[u8]) -> Option<MediaKeyStatus> {
+        let mut statuses = self.sessions.iter().filter_map(|s| s.key_statuses.get(key_id).copied());
+        let first = statuses.next()?;
+        Some(if first == MediaKeyStatus::Usable { first } else { statuses.find(|s| *s == MediaKeyStatus::Usable).unwrap_or(first) })
+    }
+    
+    /// Whether a usable key for `key_id` is loaded
+    pub fn has_key(&self, key_id: &[u8]) -> bool {
+        self.key_status(key_id) == Some(MediaKeyStatus::Usable)
+    }
+    
+    /// Decrypt a sample in place with the session holding its key
+    pub fn decrypt(&self, encryption: &SampleEncryption, data: &mut [u8]) -> Result<(), EmeError> {
+        let session = self.sessions.iter()
+            .find(|s| !s.closed && s.key_statuses.get(&encryption.key_id[..]) == Some(&MediaKeyStatus::Usable))
+            .ok_or(EmeError::KeyNotFound)?;
+        session.keys.decrypt_sample(encryption, data)
+    }
+    
+    /// Session events raised since the last call, with their session IDs
+    pub fn take_events(&mut self) -> Vec<(String, MediaKeySessionEvent)> {
+        self.sessions.iter_mut()
+            .flat_map(|s| {
+                let id = s.session_id.clone();
+                s.take_events().into_iter().map(move |e| (id.clone(), e))
+            })
+            .collect()
+    }
+    
     /// Set server certificate
     pub fn set_server_certificate(&mut self, _certificate: &[u8]) -> Result<(), EmeError> {
         // Store certificate for CDM
@@ -127,6 +178,18 @@ pub struct MediaKeySession {
     pub key_statuses: HashMap<Vec<u8>, MediaKeyStatus>,
     pub expiration: f64,
     pub closed: bool,
+    keys: ClearKey,
+    request_generated: bool,
+    events: Vec<MediaKeySessionEvent>,
+}
+
+/// Session event
+#[derive(Debug, Clone)]
+pub enum MediaKeySessionEvent {
+    /// `message` carrying a license request for the application's server
+    Message(LicenseRequest),
+    /// `keystatuseschange`
+    KeyStatusesChange,
 }
 
 /// Key status
@@ -143,52 +206,75 @@ pub enum MediaKeyStatus {
 
 impl MediaKeySession {
     /// Generate license request
+    ///
+    /// The request is also queued as a `message` event.
     pub fn generate_request(&mut self, init_data_type: &str, init_data: &[u8]) -> Result<LicenseRequest, EmeError> {
-        // Parse init data and generate request
-        let request_data = match init_data_type {
+        if self.closed || self.request_generated {
+            return Err(EmeError::InvalidState);
+        }
+        let key_ids = match init_data_type {
             "cenc" => self.parse_cenc(init_data)?,
+            "keyids" => license::parse_key_ids(init_data)?,
             "webm" => self.parse_webm(init_data)?,
             _ => return Err(EmeError::NotSupported),
         };
+        if key_ids.is_empty() {
+            return Err(EmeError::InvalidData);
+        }
+        self.request_generated = true;
         
-        Ok(LicenseRequest {
+        let request = LicenseRequest {
             message_type: LicenseMessageType::LicenseRequest,
-            message: request_data,
-        })
+            message: license::license_request(&key_ids, self.session_type),
+        };
+        self.events.push(MediaKeySessionEvent::Message(request.clone()));
+        Ok(request)
     }
     
-    fn parse_cenc(&self, init_data: &[u8]) -> Result<Vec<u8>, EmeError> {
-        // Parse CENC PSSH box
-        if init_data.len() < 32 {
-            return Err(EmeError::InvalidData);
-        }
-        Ok(init_data.to_vec())
+    fn parse_cenc(&self, init_data: &[u8]) -> Result<Vec<Vec<u8>>, EmeError> {
+        // Key IDs come from Common PSSH boxes
+        Ok(cenc::key_ids(init_data)?.into_iter().map(|k| k.to_vec()).collect())
     }
     
-    fn parse_webm(&self, init_data: &[u8]) -> Result<Vec<u8>, EmeError> {
-        Ok(init_data.to_vec())
+    fn parse_webm(&self, init_data: &[u8]) -> Result<Vec<Vec<u8>>, EmeError> {
+        // WebM init data is the key ID itself
+        if init_data.is_empty() || init_data.len() > 512 {
+            return Err(EmeError::InvalidData);
+        }
+        Ok(vec![init_data.to_vec()])
     }
     
     /// Update with license
+    ///
+    /// For Clear Key the response is a JWK Set; every key becomes usable.
     pub fn update(&mut self, response: &[u8]) -> Result<(), EmeError> {
-        // Parse license response and extract keys
-        // For Clear Key, response is JSON with keys
-        if response.len() < 10 {
-            return Err(EmeError::InvalidData);
+        if self.closed || !self.request_generated {
+            return Err(EmeError::InvalidState);
         }
-        
-        // Mark keys as usable
-        self.key_statuses.insert(vec![0; 16], MediaKeyStatus::Usable);
-        
+        for jwk in license::parse_jwk_set(response)? {
+            self.keys.add_key(jwk.key_id.clone(), jwk.key.to_vec());
+            self.key_statuses.insert(jwk.key_id, MediaKeyStatus::Usable);
+        }
+        self.events.push(MediaKeySessionEvent::KeyStatusesChange);
         Ok(())
     }
     
+    /// Events raised since the last call
+    pub fn take_events(&mut self) -> Vec<MediaKeySessionEvent> { std::mem::take(&mut self.events) }
+    
     /// Close session
     pub fn close(&mut self) -> Result<(), EmeError> {
+        if self.closed {
+            return Ok(());
+        }
         self.closed = true;
         for status in self.key_statuses.values_mut() {
             *status = MediaKeyStatus::Released;
         }
+        self.keys = ClearKey::new();
+        if !self.key_statuses.is_empty() {
+            self.events.push(MediaKeySessionEvent::KeyStatusesChange);
+        }
         Ok(())
     }
     
@@ -198,12 +284,14 @@ impl MediaKeySession {
             return Err(EmeError::InvalidState);
         }
         self.key_statuses.clear();
+        self.keys = ClearKey::new();
+        self.events.push(MediaKeySessionEvent::KeyStatusesChange);
         Ok(())
     }
 }
 
 /// License request
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LicenseRequest {
     pub message_type: LicenseMessageType,
     pub message: Vec<u8>,
@@ -240,29 +328,30 @@ impl ClearKey {
         self.keys.get(key_id)
     }
     
-    /// Decrypt sample
+    fn aes_key(&self, key_id: &[u8]) -> Result<[u8; 16], EmeError> {
+        let key = self.get_key(key_id).ok_or(EmeError::KeyNotFound)?;
+        key.as_slice().try_into().map_err(|_| EmeError::InvalidData)
+    }
+    
+    /// Decrypt a whole sample with AES-128 CTR
     pub fn decrypt(&self, key_id: &[u8], iv: &[u8], data: &mut [u8]) -> Result<(), EmeError> {
-        let _key = self.get_key(key_id).ok_or(EmeError::KeyNotFound)?;
-        
-        // AES-CTR decryption placeholder
-        // Real implementation would use AES library
-        let _ = iv;
-        
+        let key = self.aes_key(key_id)?;
+        aes::Ctr::new(&key, iv).apply(data);
         Ok(())
     }
     
-    /// Parse JSON license response
+    /// Decrypt a CENC sample, honouring its subsamples
+    pub fn decrypt_sample(&self, encryption: &SampleEncryption, data: &mut [u8]) -> Result<(), EmeError> {
+        let key = self.aes_key(&encryption.key_id)?;
+        cenc::decrypt_sample(&key, encryption, data)
+    }
+    
+    /// Parse JSON license response: `{ "keys": [{ "kty": "oct", "k": "...", "kid": "..." }] }`
     pub fn parse_license(&mut self, response: &[u8]) -> Result<(), EmeError> {
-        // Parse JSON: { "keys": [{ "kty": "oct", "k": "...", "kid": "..." }] }
-        let text = std::str::from_utf8(response).map_err(|_| EmeError::InvalidData)?;
-        
-        // Simplified parsing - real impl would use serde_json
-        if text.contains("\"keys\"") {
-            // Extract keys from JSON
-            Ok(())
-        } else {
-            Err(EmeError::InvalidData)
+        for jwk in license::parse_jwk_set(response)? {
+            self.add_key(jwk.key_id, jwk.key.to_vec());
         }
+        Ok(())
     }
 }
 
@@ -336,4 +425,38 @@ mod tests {
         ck.add_key(key_id.clone(), key);
         assert!(ck.get_key(&key_id).is_some());
     }
+    
+    #[test]
+    fn test_clear_key_session_flow() {
+        let kid = [0x11u8; 16];
+        let key = [0x22u8; 16];
+        let mut keys = MediaKeys::new(KeySystem::ClearKey);
+        let session = keys.create_session(MediaKeySessionType::Temporary);
+        let session_id = session.session_id.clone();
+        
+        assert!(matches!(session.update(b"{}"), Err(EmeError::InvalidState)));
+        let request = session.generate_request("cenc", &cenc::tests::pssh_v1(&[kid])).unwrap();
+        assert_eq!(license::parse_key_ids(&request.message).unwrap(), vec![kid.to_vec()]);
+        assert!(matches!(session.generate_request("cenc", &[]), Err(EmeError::InvalidState)));
+        
+        let encryption = SampleEncryption { scheme: *b"cenc", key_id: kid, iv: vec![3; 8], subsamples: Vec::new() };
+        let mut sample = b"plain sample".to_vec();
+        aes::Ctr::new(&key, &encryption.iv).apply(&mut sample);
+        assert!(matches!(keys.decrypt(&encryption, &mut sample), Err(EmeError::KeyNotFound)));
+        
+        let jwk = format!(
+            r#"{{"keys":[{{"kty":"oct","kid":"{}","k":"{}"}}]}}"#,
+            license::base64url_encode(&kid), license::base64url_encode(&key),
+        );
+        keys.session_mut(&session_id).unwrap().update(jwk.as_bytes()).unwrap();
+        assert!(keys.has_key(&kid));
+        keys.decrypt(&encryption, &mut sample).unwrap();
+        assert_eq!(sample, b"plain sample");
+        
+        keys.session_mut(&session_id).unwrap().close().unwrap();
+        assert_eq!(keys.key_status(&kid), Some(MediaKeyStatus::Released));
+        let events = keys.take_events();
+        assert!(matches!(events[0].1, MediaKeySessionEvent::Message(_)));
+        assert!(matches!(events[1..], [(_, MediaKeySessionEvent::KeyStatusesChange), (_, MediaKeySessionEvent::KeyStatusesChange)]));
+    }
 }
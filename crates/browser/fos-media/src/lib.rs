@@ -8,10 +8,16 @@
 //! - Web Audio API (with spatial audio)
 //! - WebRTC (with data channels, ICE, STUN)
 //! - Media codecs (H.264, H.265, VP8/VP9, AV1, AAC, Opus, Vorbis)
-//! - Encrypted Media Extensions (EME)
+//! - Encrypted Media Extensions (EME) with ClearKey CENC decryption
 //! - Container formats (MP4, WebM, MKV, MPEG-TS, fMP4)
-//! - Streaming protocols (HLS, DASH, ABR)
+//! - Streaming protocols (HLS, DASH, ABR) and progressive download
 //! - SIMD optimizations
+//! - Audio output devices (PulseAudio/ALSA, CoreAudio, WASAPI)
+//! - Screen capture (PipeWire, ScreenCaptureKit, Windows.Graphics.Capture)
+//! - Canvas capture and MediaRecorder (WebM with VP8/Opus)
+//! - Media Session with OS media keys (MPRIS, MPNowPlayingInfoCenter, SMTC)
+//! - WebVTT text tracks with cue layout
+//! - Media Capabilities (decodingInfo/encodingInfo)
 
 pub mod element;
 pub mod tracks;
@@ -27,31 +33,69 @@ pub mod containers;
 pub mod pipeline;
 pub mod streaming;
 pub mod simd;
+pub mod output;
+pub mod capture;
+pub mod session;
+pub mod vtt;
+pub mod recorder;
+pub mod capabilities;
 
 pub use element::{
     HTMLVideoElement, HTMLAudioElement, HTMLMediaElement,
     NetworkState, ReadyState, CanPlayType, TimeRanges,
 };
-pub use tracks::{TextTrack, AudioTrack, VideoTrack, TextTrackKind, TextTrackMode};
+pub use tracks::{TextTrack, TextTrackCue, TextTrackEvent, AudioTrack, VideoTrack, TextTrackKind, TextTrackMode};
+pub use vtt::{VttCue, VttFile, VttRegion, CueSettings, VttError};
+pub use vtt::layout::{CueBox, TextMeasure};
 pub use mse::{MediaSource, SourceBuffer, MediaSourceReadyState};
 pub use fullscreen::{FullscreenManager, PipManager};
 pub use audio::{
     AudioContext, AudioContextState, OscillatorNode, GainNode, AudioBuffer,
     PannerNode, StereoPannerNode, AudioWorkletNode,
+    OfflineAudioContext, AudioBufferSourceNode, BiquadFilterNode, AnalyserNode,
+    DynamicsCompressorNode, ConvolverNode,
 };
 pub use webrtc::{
     RTCPeerConnection, MediaStream, MediaStreamTrack,
-    RTCDataChannel, ScreenCapture,
+    RTCDataChannel, ScreenCapture, ScreenCaptureError, DisplayMediaRequest, DisplayMediaStreamOptions, DisplaySurface,
+    UserMedia, UserMediaError, MediaStreamConstraints, MediaTrackConstraints, MediaTrackSettings,
+    ConstrainRange, ConstrainValue,
     ice::{IceAgent, IceCandidate, IceState},
     stun::StunMessage,
     sdp::SessionDescription,
 };
-pub use codecs::{CodecType, CodecRegistry, VideoDecoder, AudioDecoder, CodecConfig};
-pub use eme::{KeySystem, MediaKeys, MediaKeySession, ClearKey};
+pub use codecs::{CodecType, CodecRegistry, VideoDecoder, AudioDecoder, VideoEncoder, AudioEncoder, CodecConfig};
+pub use eme::{KeySystem, MediaKeys, MediaKeySession, MediaKeySessionEvent, MediaKeyStatus, ClearKey, SharedMediaKeys};
 pub use decoders::{VideoFrame, AudioSamples, EncodedPacket, VideoDecoderTrait, AudioDecoderTrait};
-pub use containers::{Demuxer, TrackInfo, Packet, CodecId, ContainerFormat, detect_format};
-pub use pipeline::{MediaPipeline, PipelineState};
+pub use containers::{Demuxer, TrackInfo, Packet, CodecId, ContainerFormat, InitData, SampleEncryption, detect_format};
+pub use pipeline::{MediaPipeline, PipelineState, PipelineEvent};
+pub use pipeline::clock::{MediaClock, ClockSource};
+pub use pipeline::renderer::AudioRateMode;
+pub use pipeline::wsola::Wsola;
 pub use streaming::{Manifest, Variant, Segment, QualityLevel};
+pub use streaming::progressive::{ProgressiveLoader, ProgressiveFormat, LoadEvent};
+pub use output::{
+    AudioOutput, AudioOutputBackend, AudioDevice, AudioEngine, OutputConfig, OutputError,
+    AudioMixer, AudioRenderSource, SampleQueueSource, SharedSampleQueue, MixerInputId,
+    NullOutput, RENDER_QUANTUM_FRAMES,
+    AudioGroup, AudioGroupSource, AudioCaptureStream,
+};
+pub use capture::{
+    CaptureSource, CaptureConfig, CapturedFrame, CaptureError, ScreenCaptureBackend,
+    NullCapture, TabFrameSink,
+    CaptureDevice, CaptureDeviceBackend, MediaDeviceInfo, MediaDeviceKind, DeviceCapabilities,
+    FacingMode, NullDevices, CanvasCaptureTrack, CanvasCaptureError,
+};
+pub use recorder::{MediaRecorder, MediaRecorderOptions, RecordingState, RecorderError, RecorderEvent};
+pub use capabilities::{
+    MediaCapabilities, MediaCapabilitiesInfo, MediaCapabilitiesError, MediaDecodingConfiguration,
+    MediaEncodingConfiguration, MediaDecodingType, MediaEncodingType, VideoConfiguration, AudioConfiguration,
+};
+pub use session::{
+    MediaSession, MediaMetadata, MediaImage, MediaSessionAction, MediaSessionActionDetails,
+    MediaSessionPlaybackState, MediaPositionState, MediaSessionError, MediaSessionController,
+    MediaKeyBackend, NowPlaying, NullMediaKeys, ActionRoute,
+};
 
 /// Media error
 #[derive(Debug, thiserror::Error)]
@@ -2,7 +2,14 @@
 //!
 //! MSE API for adaptive streaming.
 
-use std::collections::VecDeque;
+use crate::codecs::CodecSupport;
+use crate::containers::{self, ContainerFormat, Demuxer, DemuxerError, Packet, TrackInfo};
+use crate::containers::fmp4::Fmp4Demuxer;
+use crate::containers::webm::WebMDemuxer;
+use std::time::Duration;
+
+/// Bytes of coded frames a single SourceBuffer may hold
+const BUFFER_QUOTA: usize = 150 * 1024 * 1024;
 
 /// Media Source ready state
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -22,6 +29,44 @@ pub struct MediaSource {
     pub active_source_buffers: Vec<usize>,
 }
 
+/// Byte stream parser for a SourceBuffer
+#[derive(Debug)]
+enum SegmentParser {
+    Mp4(Fmp4Demuxer),
+    WebM(WebMDemuxer),
+}
+
+impl SegmentParser {
+    fn for_format(format: ContainerFormat) -> Option<Self> {
+        match format {
+            ContainerFormat::Mp4 | ContainerFormat::FragmentedMp4 => Some(Self::Mp4(Fmp4Demuxer::new())),
+            ContainerFormat::WebM | ContainerFormat::Mkv => Some(Self::WebM(WebMDemuxer::streaming())),
+            ContainerFormat::MpegTs => None,
+        }
+    }
+
+    fn append(&mut self, data: &[u8]) -> Result<Vec<Packet>, DemuxerError> {
+        match self {
+            Self::Mp4(p) => p.append(data),
+            Self::WebM(p) => p.append(data),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Self::Mp4(p) => p.reset_parser(),
+            Self::WebM(p) => p.reset_parser(),
+        }
+    }
+
+    fn demuxer(&self) -> &dyn Demuxer {
+        match self {
+            Self::Mp4(p) => p,
+            Self::WebM(p) => p,
+        }
+    }
+}
+
 /// Source Buffer
 #[derive(Debug)]
 pub struct SourceBuffer {
@@ -31,7 +76,14 @@ pub struct SourceBuffer {
     pub timestamp_offset: f64,
     pub append_window_start: f64,
     pub append_window_end: f64,
-    buffer: VecDeque<u8>,
+    parser: Option<SegmentParser>,
+    /// Bytes held back until the container format can be sniffed
+    sniff: Vec<u8>,
+    /// Coded frames in decode order
+    frames: Vec<Packet>,
+    /// End of the last coded frame group, for sequence mode
+    group_end: f64,
+    read_pos: usize,
 }
 
 /// Append mode
@@ -51,32 +103,32 @@ impl MediaSource {
             active_source_buffers: Vec::new(),
         }
     }
-    
+
     /// Check if type is supported
     pub fn is_type_supported(mime_type: &str) -> bool {
-        matches!(mime_type, 
-            "video/mp4" | "video/webm" | "audio/mp4" | "audio/webm" |
-            "video/mp4; codecs=\"avc1.42E01E\"" |
-            "video/webm; codecs=\"vp8\"" |
-            "video/webm; codecs=\"vp9\""
-        )
-    }
-    
+        let (essence, _) = containers::parse_mime(mime_type);
+        let byte_stream = matches!(
+            containers::format_for_mime(&essence),
+            Some(ContainerFormat::Mp4 | ContainerFormat::WebM)
+        );
+        byte_stream && containers::type_support(mime_type) != CodecSupport::Unsupported
+    }
+
     /// Add source buffer
     pub fn add_source_buffer(&mut self, mime_type: &str) -> Result<usize, MseError> {
         if self.ready_state != MediaSourceReadyState::Open {
             return Err(MseError::InvalidState);
         }
-        
+
         if !Self::is_type_supported(mime_type) {
             return Err(MseError::NotSupported);
         }
-        
-        let buffer = SourceBuffer::new();
+
+        let buffer = SourceBuffer::with_type(mime_type)?;
         self.source_buffers.push(buffer);
         Ok(self.source_buffers.len() - 1)
     }
-    
+
     /// Remove source buffer
     pub fn remove_source_buffer(&mut self, index: usize) -> Result<(), MseError> {
         if index >= self.source_buffers.len() {
@@ -85,17 +137,17 @@ impl MediaSource {
         self.source_buffers.remove(index);
         Ok(())
     }
-    
+
     /// End of stream
     pub fn end_of_stream(&mut self, error: Option<EndOfStreamError>) {
         if error.is_none() {
             self.ready_state = MediaSourceReadyState::Ended;
         }
     }
-    
+
     /// Set live seekable range
     pub fn set_live_seekable_range(&mut self, _start: f64, _end: f64) {}
-    
+
     /// Clear live seekable range
     pub fn clear_live_seekable_range(&mut self) {}
 }
@@ -105,6 +157,7 @@ impl Default for MediaSource {
 }
 
 impl SourceBuffer {
+    /// Source buffer whose byte stream format is sniffed from the first append
     pub fn new() -> Self {
         Self {
             mode: AppendMode::Segments,
@@ -113,52 +166,233 @@ impl SourceBuffer {
             timestamp_offset: 0.0,
             append_window_start: 0.0,
             append_window_end: f64::INFINITY,
-            buffer: VecDeque::new(),
+            parser: None,
+            sniff: Vec::new(),
+            frames: Vec::new(),
+            group_end: 0.0,
+            read_pos: 0,
         }
     }
-    
+
+    /// Source buffer for a MIME type such as `video/webm; codecs="av01.0.05M.08"`
+    pub fn with_type(mime_type: &str) -> Result<Self, MseError> {
+        let mut buffer = Self::new();
+        buffer.parser = Some(Self::parser_for(mime_type)?);
+        Ok(buffer)
+    }
+
+    fn parser_for(mime_type: &str) -> Result<SegmentParser, MseError> {
+        let (essence, _) = containers::parse_mime(mime_type);
+        containers::format_for_mime(&essence).and_then(SegmentParser::for_format).ok_or(MseError::NotSupported)
+    }
+
     /// Append buffer
     pub fn append_buffer(&mut self, data: &[u8]) -> Result<(), MseError> {
         if self.updating {
             return Err(MseError::InvalidState);
         }
-        
+        let size: usize = self.frames.iter().map(|f| f.data.len()).sum();
+        if size + data.len() > BUFFER_QUOTA {
+            return Err(MseError::QuotaExceeded);
+        }
+
         self.updating = true;
-        self.buffer.extend(data);
+        let result = self.run_append(data);
         self.updating = false;
+        result
+    }
+
+    fn run_append(&mut self, data: &[u8]) -> Result<(), MseError> {
+        let parser = match &mut self.parser {
+            Some(parser) => parser,
+            None => {
+                self.sniff.extend_from_slice(data);
+                if self.sniff.len() < 12 { return Ok(()); }
+                let format = containers::detect_format(&self.sniff).ok_or(MseError::NotSupported)?;
+                self.parser.insert(SegmentParser::for_format(format).ok_or(MseError::NotSupported)?)
+            }
+        };
+        let sniffed = std::mem::take(&mut self.sniff);
+        let input = if sniffed.is_empty() { data } else { &sniffed };
+        let packets = parser.append(input).map_err(MseError::Parse)?;
+
+        for (i, packet) in packets.into_iter().enumerate() {
+            if self.mode == AppendMode::Sequence && i == 0 {
+                // Each append continues where the previous coded frame group ended
+                self.timestamp_offset = self.group_end - packet.pts.as_secs_f64();
+            }
+            let Some(packet) = self.apply_offset(packet) else { continue };
+            let start = packet.pts.as_secs_f64();
+            let end = start + packet.duration.as_secs_f64();
+            if start < self.append_window_start || end > self.append_window_end {
+                continue;
+            }
+            self.group_end = self.group_end.max(end);
+            self.insert_frame(packet);
+        }
+        self.update_buffered();
         Ok(())
     }
-    
+
+    /// Shift a frame by `timestamp_offset`, dropping frames that would start before zero
+    fn apply_offset(&self, mut packet: Packet) -> Option<Packet> {
+        let shift = |t: Duration| {
+            let nanos = ((t.as_secs_f64() + self.timestamp_offset) * 1e9).round();
+            (nanos >= 0.0).then(|| Duration::from_nanos(nanos as u64))
+        };
+        packet.pts = shift(packet.pts)?;
+        packet.dts = shift(packet.dts).unwrap_or(Duration::ZERO);
+        Some(packet)
+    }
+
+    /// Insert in decode order, replacing frames of the same track that the
+    /// new frame overlaps
+    fn insert_frame(&mut self, packet: Packet) {
+        let start = packet.pts;
+        let end = packet.pts + packet.duration.max(Duration::from_nanos(1));
+        self.frames.retain(|f| f.track_id != packet.track_id || f.pts < start || f.pts >= end);
+        let index = self.frames.partition_point(|f| f.dts <= packet.dts);
+        if index < self.read_pos { self.read_pos += 1; }
+        self.frames.insert(index, packet);
+        self.read_pos = self.read_pos.min(self.frames.len());
+    }
+
+    /// Recompute `buffered`: the intersection of every track's ranges
+    fn update_buffered(&mut self) {
+        let mut track_ids: Vec<u32> = self.frames.iter().map(|f| f.track_id).collect();
+        track_ids.sort_unstable();
+        track_ids.dedup();
+
+        let mut result: Option<Vec<(f64, f64)>> = None;
+        for id in track_ids {
+            let mut frames: Vec<(f64, f64)> = self.frames.iter().filter(|f| f.track_id == id)
+                .map(|f| (f.pts.as_secs_f64(), (f.pts + f.duration).as_secs_f64())).collect();
+            frames.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut ranges: Vec<(f64, f64)> = Vec::new();
+            let mut last_duration = 0.0f64;
+            for (start, end) in frames {
+                // Gaps shorter than a frame are not considered discontinuities
+                match ranges.last_mut() {
+                    Some(last) if start <= last.1 + last_duration.max(1e-3) => last.1 = last.1.max(end),
+                    _ => ranges.push((start, end)),
+                }
+                last_duration = end - start;
+            }
+            result = Some(match result {
+                None => ranges,
+                Some(previous) => intersect(&previous, &ranges),
+            });
+        }
+
+        self.buffered = super::element::TimeRanges::new();
+        for (start, end) in result.unwrap_or_default() {
+            self.buffered.add(start, end);
+        }
+    }
+
     /// Abort
     pub fn abort(&mut self) -> Result<(), MseError> {
+        if let Some(parser) = &mut self.parser { parser.reset(); }
+        self.sniff.clear();
         self.updating = false;
         Ok(())
     }
-    
+
     /// Remove buffered range
-    pub fn remove(&mut self, _start: f64, _end: f64) -> Result<(), MseError> {
+    pub fn remove(&mut self, start: f64, end: f64) -> Result<(), MseError> {
         if self.updating {
             return Err(MseError::InvalidState);
         }
+        if start < 0.0 || end <= start {
+            return Err(MseError::TypeError);
+        }
+        // Frames depending on removed ones go too, up to the next key frame
+        let mut dropping: Vec<u32> = Vec::new();
+        let mut removed_before_read = 0;
+        let mut index = 0;
+        self.frames.retain(|f| {
+            let pts = f.pts.as_secs_f64();
+            let in_range = pts >= start && pts < end;
+            if in_range {
+                if !dropping.contains(&f.track_id) { dropping.push(f.track_id); }
+            } else if f.is_key {
+                dropping.retain(|&t| t != f.track_id);
+            }
+            let keep = !in_range && !dropping.contains(&f.track_id);
+            if !keep && index < self.read_pos { removed_before_read += 1; }
+            index += 1;
+            keep
+        });
+        self.read_pos -= removed_before_read;
+        self.update_buffered();
         Ok(())
     }
-    
+
     /// Change type
-    pub fn change_type(&mut self, _mime_type: &str) -> Result<(), MseError> {
+    pub fn change_type(&mut self, mime_type: &str) -> Result<(), MseError> {
+        if mime_type.is_empty() {
+            return Err(MseError::TypeError);
+        }
+        if !MediaSource::is_type_supported(mime_type) {
+            return Err(MseError::NotSupported);
+        }
+        if self.updating {
+            return Err(MseError::InvalidState);
+        }
+        self.parser = Some(Self::parser_for(mime_type)?);
+        self.sniff.clear();
         Ok(())
     }
+
+    /// Video track described by the last initialization segment
+    pub fn video_track(&self) -> Option<&TrackInfo> {
+        self.parser.as_ref().and_then(|p| p.demuxer().video_track())
+    }
+
+    /// Audio track described by the last initialization segment
+    pub fn audio_track(&self) -> Option<&TrackInfo> {
+        self.parser.as_ref().and_then(|p| p.demuxer().audio_track())
+    }
+
+    /// Next buffered coded frame in decode order, for the playback pipeline
+    pub fn next_frame(&mut self) -> Option<Packet> {
+        let frame = self.frames.get(self.read_pos).cloned();
+        if frame.is_some() { self.read_pos += 1; }
+        frame
+    }
+
+    /// Position reading at the key frame needed to present `time` (seconds)
+    pub fn seek(&mut self, time: f64) {
+        let video = self.video_track().map(|t| t.track_id);
+        let position = Duration::from_secs_f64(time.max(0.0));
+        self.read_pos = containers::mp4::seek_index(&self.frames, video, position, |p| (p.track_id, p.pts, p.dts, p.is_key));
+    }
 }
 
 impl Default for SourceBuffer {
     fn default() -> Self { Self::new() }
 }
 
+fn intersect(a: &[(f64, f64)], b: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut out = Vec::new();
+    for &(a_start, a_end) in a {
+        for &(b_start, b_end) in b {
+            let (start, end) = (a_start.max(b_start), a_end.min(b_end));
+            if start < end { out.push((start, end)); }
+        }
+    }
+    out
+}
+
 /// MSE error
 #[derive(Debug, Clone)]
 pub enum MseError {
     InvalidState,
     NotSupported,
     QuotaExceeded,
+    TypeError,
+    /// The appended bytes are not a valid byte stream
+    Parse(DemuxerError),
 }
 
 /// End of stream error
@@ -171,20 +405,70 @@ pub enum EndOfStreamError {
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+    use fos_demux::fixtures::{init_segment, media_segment};
+
     #[test]
     fn test_media_source() {
         let mut ms = MediaSource::new();
         ms.ready_state = MediaSourceReadyState::Open;
-        
+
         let idx = ms.add_source_buffer("video/mp4").unwrap();
         assert_eq!(idx, 0);
+        assert!(ms.add_source_buffer("video/mp4; codecs=\"vp8\"").is_err());
+        assert!(ms.add_source_buffer("video/webm; codecs=\"vp8\"").is_err());
+        assert!(ms.add_source_buffer("video/webm; codecs=\"vp9\"").is_err());
+        assert!(ms.add_source_buffer("video/webm; codecs=\"av01.0.05M.08\"").is_ok());
+        assert!(ms.add_source_buffer("video/mp2t").is_err());
     }
-    
+
     #[test]
     fn test_source_buffer() {
         let mut sb = SourceBuffer::new();
         sb.append_buffer(&[1, 2, 3, 4]).unwrap();
         assert!(!sb.updating);
     }
+
+    #[test]
+    fn test_fmp4_append() {
+        let mut sb = SourceBuffer::with_type("video/mp4; codecs=\"avc1.42E01E\"").unwrap();
+        let init = init_segment(&[], true);
+        let (head, tail) = init.split_at(20);
+        sb.append_buffer(head).unwrap();
+        sb.append_buffer(tail).unwrap();
+        assert_eq!(sb.video_track().unwrap().width, Some(320));
+        assert_eq!(sb.buffered.length(), 0);
+
+        sb.append_buffer(&media_segment(0, &[3, 2])).unwrap();
+        sb.append_buffer(&media_segment(400, &[1])).unwrap();
+        assert_eq!(sb.buffered.length(), 2);
+        assert_eq!((sb.buffered.start(0), sb.buffered.end(0)), (Some(0.0), Some(0.2)));
+
+        sb.seek(0.45);
+        assert_eq!(sb.next_frame().unwrap().pts, Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_sequence_mode_and_remove() {
+        let mut sb = SourceBuffer::new();
+        sb.mode = AppendMode::Sequence;
+        sb.append_buffer(&init_segment(&[], true)).unwrap();
+        sb.append_buffer(&media_segment(5000, &[1, 1])).unwrap();
+        sb.append_buffer(&media_segment(9000, &[1])).unwrap();
+        assert_eq!(sb.buffered.length(), 1);
+        assert_eq!(sb.buffered.end(0), Some(0.3));
+
+        // Removing the key frame takes the dependent frame with it
+        sb.remove(0.0, 0.05).unwrap();
+        assert_eq!(sb.buffered.start(0), Some(0.2));
+        assert_eq!(sb.next_frame().unwrap().pts, Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_append_window() {
+        let mut sb = SourceBuffer::with_type("video/mp4").unwrap();
+        sb.append_window_end = 0.15;
+        sb.append_buffer(&init_segment(&[], true)).unwrap();
+        sb.append_buffer(&media_segment(0, &[1, 1])).unwrap();
+        assert_eq!(sb.buffered.end(0), Some(0.1));
+    }
 }
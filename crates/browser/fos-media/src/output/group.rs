@@ -0,0 +1,221 @@
+//! Audio Groups
+//!
+//! Sub-mixers that collect all audio of one browsing context (tab):
+//! level metering for audible indicators and capture taps of the mixed output.
+
+use super::pump::{AudioMixer, AudioRenderSource, MixerInputId};
+use super::ring::{audio_ring_buffer, RingConsumer, RingProducer};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Peak level below which a quantum counts as silent (~ -60 dBFS)
+const SILENCE_THRESHOLD: f32 = 0.001;
+
+/// How long a group stays "audible" after its last non-silent quantum
+const AUDIBLE_HOLD_SECONDS: f64 = 2.0;
+
+/// Capture buffer length in seconds
+const CAPTURE_BUFFER_SECONDS: usize = 2;
+
+struct CaptureTap {
+    producer: RingProducer,
+    alive: Arc<AtomicBool>,
+}
+
+/// Mixed output of one group plus metering and capture
+pub struct AudioGroupSource {
+    mixer: AudioMixer,
+    /// Peak of the most recent quantum
+    peak: f32,
+    /// Seconds since the last non-silent quantum
+    silent_for: f64,
+    taps: Vec<CaptureTap>,
+    channels: u32,
+    sample_rate: u32,
+}
+
+impl std::fmt::Debug for AudioGroupSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioGroupSource")
+            .field("peak", &self.peak)
+            .field("audible", &self.is_audible())
+            .field("captures", &self.taps.len())
+            .finish()
+    }
+}
+
+impl AudioGroupSource {
+    pub fn new() -> Self {
+        Self {
+            mixer: AudioMixer::new(),
+            peak: 0.0,
+            silent_for: f64::INFINITY,
+            taps: Vec::new(),
+            channels: 2,
+            sample_rate: 48000,
+        }
+    }
+
+    pub fn mixer_mut(&mut self) -> &mut AudioMixer { &mut self.mixer }
+
+    pub fn add_input(&mut self, source: Box<dyn AudioRenderSource>) -> MixerInputId {
+        self.mixer.add_input(source)
+    }
+
+    pub fn remove_input(&mut self, id: MixerInputId) -> Option<Box<dyn AudioRenderSource>> {
+        self.mixer.remove_input(id)
+    }
+
+    /// Peak sample magnitude of the last rendered quantum
+    pub fn peak(&self) -> f32 { self.peak }
+
+    /// Start capturing the group's mixed output
+    pub fn capture(&mut self) -> AudioCaptureStream {
+        let capacity = self.sample_rate as usize * self.channels as usize * CAPTURE_BUFFER_SECONDS;
+        let (producer, consumer) = audio_ring_buffer(capacity);
+        let alive = Arc::new(AtomicBool::new(true));
+        self.taps.push(CaptureTap { producer, alive: Arc::clone(&alive) });
+        AudioCaptureStream {
+            consumer,
+            alive,
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Number of active captures
+    pub fn capture_count(&self) -> usize {
+        self.taps.iter().filter(|t| t.alive.load(Ordering::Acquire)).count()
+    }
+}
+
+impl Default for AudioGroupSource {
+    fn default() -> Self { Self::new() }
+}
+
+impl AudioRenderSource for AudioGroupSource {
+    fn render(&mut self, output: &mut [f32], channels: u32, sample_rate: u32) {
+        self.channels = channels;
+        self.sample_rate = sample_rate;
+        self.mixer.render(output, channels, sample_rate);
+
+        self.peak = output.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        if self.peak > SILENCE_THRESHOLD {
+            self.silent_for = 0.0;
+        } else {
+            let frames = output.len() / channels.max(1) as usize;
+            self.silent_for += frames as f64 / sample_rate.max(1) as f64;
+        }
+
+        // Captures see the mix before any mute applied by the parent mixer
+        self.taps.retain(|t| t.alive.load(Ordering::Acquire));
+        for tap in &mut self.taps {
+            tap.producer.push(output);
+        }
+    }
+
+    fn is_audible(&self) -> bool {
+        self.silent_for < AUDIBLE_HOLD_SECONDS
+    }
+}
+
+/// Shared handle to a group rendered by the output mixer
+pub type AudioGroup = Arc<Mutex<AudioGroupSource>>;
+
+/// Captured mixed audio of a group (tabCapture-style)
+pub struct AudioCaptureStream {
+    consumer: RingConsumer,
+    alive: Arc<AtomicBool>,
+    channels: u32,
+    sample_rate: u32,
+}
+
+impl std::fmt::Debug for AudioCaptureStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioCaptureStream")
+            .field("channels", &self.channels)
+            .field("sample_rate", &self.sample_rate)
+            .field("available", &self.consumer.available())
+            .finish()
+    }
+}
+
+impl AudioCaptureStream {
+    pub fn channels(&self) -> u32 { self.channels }
+    pub fn sample_rate(&self) -> u32 { self.sample_rate }
+
+    /// Interleaved samples ready to read
+    pub fn available(&self) -> usize { self.consumer.available() }
+
+    /// Read captured interleaved samples
+    pub fn read(&mut self, out: &mut [f32]) -> usize {
+        self.consumer.pop(out)
+    }
+
+    /// Stop capturing
+    pub fn stop(&mut self) {
+        self.alive.store(false, Ordering::Release);
+    }
+
+    pub fn is_active(&self) -> bool { self.alive.load(Ordering::Acquire) }
+}
+
+impl Drop for AudioCaptureStream {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Tone(f32);
+    impl AudioRenderSource for Tone {
+        fn render(&mut self, output: &mut [f32], _channels: u32, _sample_rate: u32) {
+            output.iter_mut().for_each(|s| *s += self.0);
+        }
+    }
+
+    #[test]
+    fn test_audible_hold() {
+        let mut group = AudioGroupSource::new();
+        assert!(!group.is_audible());
+
+        let input = group.add_input(Box::new(Tone(0.5)));
+        let mut out = vec![0.0; 256];
+        group.render(&mut out, 2, 48000);
+        assert!(group.is_audible());
+        assert_eq!(group.peak(), 0.5);
+
+        // Silence keeps the indicator for the hold time
+        group.remove_input(input);
+        out.fill(0.0);
+        group.render(&mut out, 2, 48000);
+        assert!(group.is_audible());
+        let quanta = (AUDIBLE_HOLD_SECONDS * 48000.0 / 128.0) as usize + 1;
+        for _ in 0..quanta {
+            out.fill(0.0);
+            group.render(&mut out, 2, 48000);
+        }
+        assert!(!group.is_audible());
+    }
+
+    #[test]
+    fn test_capture_tap() {
+        let mut group = AudioGroupSource::new();
+        group.add_input(Box::new(Tone(0.25)));
+        let mut stream = group.capture();
+        assert_eq!(group.capture_count(), 1);
+
+        let mut out = vec![0.0; 8];
+        group.render(&mut out, 2, 48000);
+        let mut captured = [0.0; 8];
+        assert_eq!(stream.read(&mut captured), 8);
+        assert_eq!(captured, [0.25; 8]);
+
+        drop(stream);
+        group.render(&mut out, 2, 48000);
+        assert_eq!(group.capture_count(), 0);
+    }
+}
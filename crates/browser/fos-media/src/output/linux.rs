@@ -0,0 +1,182 @@
+//! Linux Audio Output (PulseAudio, ALSA)
+//!
+//! PulseAudio is preferred when a server is running; ALSA is the fallback.
+//!
+//! This is a stub implementation for the stream itself. Device enumeration
+//! reads /proc/asound; full implementation would use libpulse-simple
+//! (pa_simple_write) and alsa-lib (snd_pcm_writei).
+
+use super::{AudioDevice, AudioOutputBackend, NullOutput, OutputConfig, OutputError, OutputResult};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Parse /proc/asound/pcm lines ("00-00: ALC892 Analog : ALC892 Analog : playback 1 : capture 1")
+fn parse_asound_pcm(contents: &str) -> Vec<AudioDevice> {
+    contents.lines().filter_map(|line| {
+        let (ids, rest) = line.split_once(':')?;
+        if !rest.contains("playback") {
+            return None;
+        }
+        let (card, device) = ids.trim().split_once('-')?;
+        let card: u32 = card.parse().ok()?;
+        let device: u32 = device.parse().ok()?;
+        let name = rest.split(':').next()?.trim().to_string();
+        Some(AudioDevice {
+            id: format!("hw:{},{}", card, device),
+            name,
+            channels: 2,
+            sample_rates: vec![44100, 48000],
+            is_default: card == 0 && device == 0,
+        })
+    }).collect()
+}
+
+fn alsa_devices() -> Vec<AudioDevice> {
+    std::fs::read_to_string("/proc/asound/pcm")
+        .map(|s| parse_asound_pcm(&s))
+        .unwrap_or_default()
+}
+
+/// ALSA PCM output
+#[derive(Debug, Default)]
+pub struct AlsaOutput {
+    device: Option<String>,
+    // In real impl: snd_pcm_t handle
+    sink: NullOutput,
+}
+
+impl AlsaOutput {
+    pub fn new() -> Self { Self::default() }
+
+    /// ALSA is usable when the kernel exposes sound cards
+    pub fn is_available() -> bool {
+        !alsa_devices().is_empty()
+    }
+}
+
+impl AudioOutputBackend for AlsaOutput {
+    fn name(&self) -> &'static str { "alsa" }
+
+    fn enumerate_devices(&self) -> Vec<AudioDevice> {
+        alsa_devices()
+    }
+
+    fn open(&mut self, device_id: Option<&str>, config: OutputConfig) -> OutputResult<OutputConfig> {
+        let devices = alsa_devices();
+        let device = match device_id {
+            Some(id) => devices.iter().find(|d| d.id == id),
+            None => devices.iter().find(|d| d.is_default).or(devices.first()),
+        }.ok_or_else(|| OutputError::DeviceNotFound(device_id.unwrap_or("default").to_string()))?;
+
+        // Real impl would:
+        // 1. snd_pcm_open(device.id, SND_PCM_STREAM_PLAYBACK)
+        // 2. snd_pcm_hw_params: FLOAT_LE, interleaved, rate, channels, period size
+        // 3. snd_pcm_prepare
+        self.device = Some(device.id.clone());
+        self.sink.open(device_id, config)
+    }
+
+    fn writable_frames(&self) -> usize {
+        // Real impl: snd_pcm_avail_update
+        self.sink.writable_frames()
+    }
+
+    fn write(&mut self, samples: &[f32]) -> OutputResult<usize> {
+        // Real impl: snd_pcm_writei, snd_pcm_recover on -EPIPE (underrun)
+        self.sink.write(samples)
+    }
+
+    fn latency(&self) -> Duration { self.sink.latency() }
+
+    fn close(&mut self) {
+        // Real impl: snd_pcm_drain, snd_pcm_close
+        self.device = None;
+        self.sink.close();
+    }
+
+    fn is_open(&self) -> bool { self.sink.is_open() }
+}
+
+/// PulseAudio (or PipeWire-pulse) output
+#[derive(Debug, Default)]
+pub struct PulseAudioOutput {
+    // In real impl: pa_simple connection
+    sink: NullOutput,
+}
+
+impl PulseAudioOutput {
+    pub fn new() -> Self { Self::default() }
+
+    /// Native protocol socket of the user's PulseAudio server
+    fn socket_path() -> Option<PathBuf> {
+        if let Ok(server) = std::env::var("PULSE_SERVER") {
+            return Some(PathBuf::from(server.trim_start_matches("unix:")));
+        }
+        let runtime = std::env::var("XDG_RUNTIME_DIR").ok()?;
+        Some(PathBuf::from(runtime).join("pulse").join("native"))
+    }
+
+    /// A PulseAudio server is reachable
+    pub fn is_available() -> bool {
+        Self::socket_path().map(|p| p.exists()).unwrap_or(false)
+    }
+}
+
+impl AudioOutputBackend for PulseAudioOutput {
+    fn name(&self) -> &'static str { "pulseaudio" }
+
+    fn enumerate_devices(&self) -> Vec<AudioDevice> {
+        // Real impl would list sinks via pa_context_get_sink_info_list;
+        // the default sink is always addressable.
+        let mut devices = vec![AudioDevice {
+            id: "@DEFAULT_SINK@".into(),
+            name: "Default".into(),
+            channels: 2,
+            sample_rates: vec![44100, 48000],
+            is_default: true,
+        }];
+        devices.extend(alsa_devices().into_iter().map(|d| AudioDevice { is_default: false, ..d }));
+        devices
+    }
+
+    fn open(&mut self, device_id: Option<&str>, config: OutputConfig) -> OutputResult<OutputConfig> {
+        if !Self::is_available() {
+            return Err(OutputError::Unavailable("PulseAudio server not running".into()));
+        }
+        // Real impl: pa_simple_new with PA_SAMPLE_FLOAT32LE, tlength = period * 2
+        self.sink.open(device_id, config)
+    }
+
+    fn writable_frames(&self) -> usize { self.sink.writable_frames() }
+
+    fn write(&mut self, samples: &[f32]) -> OutputResult<usize> {
+        // Real impl: pa_simple_write (blocking) from the audio thread
+        self.sink.write(samples)
+    }
+
+    fn latency(&self) -> Duration {
+        // Real impl: pa_simple_get_latency
+        self.sink.latency()
+    }
+
+    fn close(&mut self) { self.sink.close(); }
+    fn is_open(&self) -> bool { self.sink.is_open() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_asound_pcm() {
+        let devices = parse_asound_pcm(
+            "00-00: ALC892 Analog : ALC892 Analog : playback 1 : capture 1\n\
+             00-02: ALC892 Alt Analog : ALC892 Alt Analog : capture 1\n\
+             01-03: HDMI 0 : HDMI 0 : playback 1\n",
+        );
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].id, "hw:0,0");
+        assert!(devices[0].is_default);
+        assert_eq!(devices[1].name, "HDMI 0");
+    }
+}
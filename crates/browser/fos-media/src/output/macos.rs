@@ -0,0 +1,52 @@
+//! CoreAudio Output (macOS)
+//!
+//! This is a stub implementation. Full implementation would use an
+//! AudioUnit (kAudioUnitSubType_DefaultOutput / HALOutput) with a render
+//! callback pulling from the ring buffer.
+
+use super::{AudioDevice, AudioOutputBackend, NullOutput, OutputConfig, OutputResult};
+use std::time::Duration;
+
+/// CoreAudio output unit
+#[derive(Debug, Default)]
+pub struct CoreAudioOutput {
+    // In real impl: AudioComponentInstance, AudioDeviceID
+    sink: NullOutput,
+}
+
+impl CoreAudioOutput {
+    pub fn new() -> Self { Self::default() }
+}
+
+impl AudioOutputBackend for CoreAudioOutput {
+    fn name(&self) -> &'static str { "coreaudio" }
+
+    fn enumerate_devices(&self) -> Vec<AudioDevice> {
+        // Real impl: AudioObjectGetPropertyData(kAudioHardwarePropertyDevices),
+        // filtered to devices with output streams
+        vec![AudioDevice {
+            id: "default".into(),
+            name: "System Output".into(),
+            channels: 2,
+            sample_rates: vec![44100, 48000],
+            is_default: true,
+        }]
+    }
+
+    fn open(&mut self, device_id: Option<&str>, config: OutputConfig) -> OutputResult<OutputConfig> {
+        // Real impl: AudioComponentInstanceNew, set kAudioOutputUnitProperty_CurrentDevice,
+        // kAudioUnitProperty_StreamFormat (Float32, interleaved), AudioOutputUnitStart
+        self.sink.open(device_id, config)
+    }
+
+    fn writable_frames(&self) -> usize { self.sink.writable_frames() }
+    fn write(&mut self, samples: &[f32]) -> OutputResult<usize> { self.sink.write(samples) }
+
+    fn latency(&self) -> Duration {
+        // Real impl: kAudioDevicePropertyLatency + kAudioDevicePropertyBufferFrameSize
+        self.sink.latency()
+    }
+
+    fn close(&mut self) { self.sink.close(); }
+    fn is_open(&self) -> bool { self.sink.is_open() }
+}
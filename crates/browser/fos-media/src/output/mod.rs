@@ -0,0 +1,448 @@
+//! Audio Output
+//!
+//! Audio output devices behind one backend trait, fed from a ring buffer
+//! that the render pump fills on the audio thread.
+//!
+//! Backends:
+//! - Linux: PulseAudio (preferred), ALSA
+//! - macOS: CoreAudio
+//! - Windows: WASAPI
+//! - Null: consumes audio in real time without a device (tests, headless)
+
+pub mod ring;
+pub mod pump;
+pub mod group;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+#[cfg(target_os = "linux")]
+pub use linux::{AlsaOutput, PulseAudioOutput};
+#[cfg(target_os = "macos")]
+pub use macos::CoreAudioOutput;
+#[cfg(target_os = "windows")]
+pub use windows::WasapiOutput;
+
+pub use group::{AudioGroup, AudioGroupSource, AudioCaptureStream};
+pub use ring::{audio_ring_buffer, RingConsumer, RingProducer};
+pub use pump::{
+    AudioRenderSource, AudioMixer, MixerInputId, RenderPump, SampleQueueSource,
+    SharedSampleQueue, RENDER_QUANTUM_FRAMES,
+};
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Output device description
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioDevice {
+    /// Backend-specific identifier (e.g. "hw:0,0")
+    pub id: String,
+    /// Human-readable name
+    pub name: String,
+    /// Maximum output channels
+    pub channels: u32,
+    /// Supported sample rates
+    pub sample_rates: Vec<u32>,
+    /// System default device
+    pub is_default: bool,
+}
+
+/// Stream configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputConfig {
+    pub sample_rate: u32,
+    pub channels: u32,
+    /// Device period in frames
+    pub period_frames: u32,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self { sample_rate: 48000, channels: 2, period_frames: 512 }
+    }
+}
+
+impl OutputConfig {
+    /// Period length as a duration
+    pub fn period(&self) -> Duration {
+        Duration::from_secs_f64(self.period_frames as f64 / self.sample_rate.max(1) as f64)
+    }
+}
+
+/// Audio output error
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum OutputError {
+    #[error("Audio backend unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Device not found: {0}")]
+    DeviceNotFound(String),
+
+    #[error("Unsupported configuration: {0}")]
+    UnsupportedConfig(String),
+
+    #[error("Stream not open")]
+    NotOpen,
+
+    #[error("Device error: {0}")]
+    Device(String),
+}
+
+/// Result type for output operations
+pub type OutputResult<T> = Result<T, OutputError>;
+
+/// Platform audio output backend
+pub trait AudioOutputBackend: Send {
+    /// Backend name ("pulseaudio", "alsa", "coreaudio", "wasapi", "null")
+    fn name(&self) -> &'static str;
+
+    /// List output devices
+    fn enumerate_devices(&self) -> Vec<AudioDevice>;
+
+    /// Open a stream on a device (`None` = system default)
+    fn open(&mut self, device_id: Option<&str>, config: OutputConfig) -> OutputResult<OutputConfig>;
+
+    /// Frames the device can accept right now
+    fn writable_frames(&self) -> usize;
+
+    /// Write interleaved samples, returning frames accepted
+    fn write(&mut self, samples: &[f32]) -> OutputResult<usize>;
+
+    /// Device latency
+    fn latency(&self) -> Duration;
+
+    /// Close the stream
+    fn close(&mut self);
+
+    fn is_open(&self) -> bool;
+}
+
+/// Create the preferred backend for this platform
+pub fn create_output_backend() -> Box<dyn AudioOutputBackend> {
+    #[cfg(target_os = "linux")]
+    {
+        if PulseAudioOutput::is_available() {
+            return Box::new(PulseAudioOutput::new());
+        }
+        if AlsaOutput::is_available() {
+            return Box::new(AlsaOutput::new());
+        }
+        Box::new(NullOutput::new())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Box::new(CoreAudioOutput::new())
+    }
+    #[cfg(target_os = "windows")]
+    {
+        Box::new(WasapiOutput::new())
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        Box::new(NullOutput::new())
+    }
+}
+
+/// Output that discards audio at the device rate
+#[derive(Debug, Default)]
+pub struct NullOutput {
+    config: Option<OutputConfig>,
+    last_write: Option<std::time::Instant>,
+    /// Frames accepted but not yet "played"
+    queued_frames: usize,
+    frames_written: u64,
+}
+
+impl NullOutput {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn frames_written(&self) -> u64 { self.frames_written }
+
+    /// Drain queued frames according to wall-clock time
+    fn advance(&mut self) {
+        let Some(config) = self.config else { return };
+        let now = std::time::Instant::now();
+        if let Some(last) = self.last_write {
+            let played = (now.duration_since(last).as_secs_f64() * config.sample_rate as f64) as usize;
+            self.queued_frames = self.queued_frames.saturating_sub(played);
+        }
+        self.last_write = Some(now);
+    }
+}
+
+impl AudioOutputBackend for NullOutput {
+    fn name(&self) -> &'static str { "null" }
+
+    fn enumerate_devices(&self) -> Vec<AudioDevice> {
+        vec![AudioDevice {
+            id: "null".into(),
+            name: "Null Output".into(),
+            channels: 8,
+            sample_rates: vec![44100, 48000, 96000],
+            is_default: true,
+        }]
+    }
+
+    fn open(&mut self, _device_id: Option<&str>, config: OutputConfig) -> OutputResult<OutputConfig> {
+        self.config = Some(config);
+        self.queued_frames = 0;
+        self.last_write = None;
+        Ok(config)
+    }
+
+    fn writable_frames(&self) -> usize {
+        match self.config {
+            // Two periods of buffering
+            Some(c) => (c.period_frames as usize * 2).saturating_sub(self.queued_frames),
+            None => 0,
+        }
+    }
+
+    fn write(&mut self, samples: &[f32]) -> OutputResult<usize> {
+        let config = self.config.ok_or(OutputError::NotOpen)?;
+        self.advance();
+        let frames = (samples.len() / config.channels.max(1) as usize).min(self.writable_frames());
+        self.queued_frames += frames;
+        self.frames_written += frames as u64;
+        Ok(frames)
+    }
+
+    fn latency(&self) -> Duration {
+        self.config.map(|c| c.period() * 2).unwrap_or_default()
+    }
+
+    fn close(&mut self) { self.config = None; }
+    fn is_open(&self) -> bool { self.config.is_some() }
+}
+
+/// Output device manager: device selection plus ring-to-device transfer
+pub struct AudioOutput {
+    backend: Box<dyn AudioOutputBackend>,
+    consumer: Option<RingConsumer>,
+    config: OutputConfig,
+    selected_device: Option<String>,
+    scratch: Vec<f32>,
+}
+
+impl std::fmt::Debug for AudioOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioOutput")
+            .field("backend", &self.backend.name())
+            .field("device", &self.selected_device)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl AudioOutput {
+    /// Output using the platform's preferred backend
+    pub fn new() -> Self {
+        Self::with_backend(create_output_backend())
+    }
+
+    pub fn with_backend(backend: Box<dyn AudioOutputBackend>) -> Self {
+        Self {
+            backend,
+            consumer: None,
+            config: OutputConfig::default(),
+            selected_device: None,
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn backend_name(&self) -> &'static str { self.backend.name() }
+
+    /// Available output devices
+    pub fn devices(&self) -> Vec<AudioDevice> {
+        self.backend.enumerate_devices()
+    }
+
+    pub fn selected_device(&self) -> Option<&str> { self.selected_device.as_deref() }
+
+    /// Negotiated stream configuration
+    pub fn config(&self) -> OutputConfig { self.config }
+
+    /// Open the stream and return the producer the render pump writes into.
+    /// The ring holds four device periods.
+    pub fn start(&mut self, device_id: Option<&str>, config: OutputConfig) -> OutputResult<RingProducer> {
+        if let Some(id) = device_id {
+            if !self.devices().iter().any(|d| d.id == id) {
+                return Err(OutputError::DeviceNotFound(id.to_string()));
+            }
+        }
+        self.backend.close();
+        self.config = self.backend.open(device_id, config)?;
+        self.selected_device = device_id.map(String::from);
+
+        let capacity = (self.config.period_frames * self.config.channels * 4) as usize;
+        let (producer, consumer) = audio_ring_buffer(capacity);
+        self.consumer = Some(consumer);
+        Ok(producer)
+    }
+
+    /// Switch to another device, keeping the stream configuration.
+    /// Returns a fresh producer; the old one is orphaned.
+    pub fn select_device(&mut self, device_id: Option<&str>) -> OutputResult<RingProducer> {
+        let config = self.config;
+        self.start(device_id, config)
+    }
+
+    /// Move buffered audio to the device; returns frames written.
+    /// Called on the audio thread after the pump has filled the ring.
+    pub fn pump(&mut self) -> OutputResult<usize> {
+        let consumer = self.consumer.as_mut().ok_or(OutputError::NotOpen)?;
+        let channels = self.config.channels.max(1) as usize;
+        let frames = self.backend.writable_frames().min(consumer.available() / channels);
+        if frames == 0 {
+            return Ok(0);
+        }
+        self.scratch.resize(frames * channels, 0.0);
+        consumer.pop(&mut self.scratch);
+        self.backend.write(&self.scratch)
+    }
+
+    /// Drop buffered audio (seek, pause)
+    pub fn flush(&mut self) {
+        if let Some(consumer) = self.consumer.as_mut() {
+            consumer.clear();
+        }
+    }
+
+    /// Total output latency: ring contents plus device latency
+    pub fn latency(&self) -> Duration {
+        let buffered = self.consumer.as_ref().map(|c| c.available()).unwrap_or(0);
+        let frames = buffered / self.config.channels.max(1) as usize;
+        Duration::from_secs_f64(frames as f64 / self.config.sample_rate.max(1) as f64) + self.backend.latency()
+    }
+
+    pub fn stop(&mut self) {
+        self.backend.close();
+        self.consumer = None;
+    }
+
+    pub fn is_running(&self) -> bool { self.backend.is_open() && self.consumer.is_some() }
+}
+
+impl Default for AudioOutput {
+    fn default() -> Self { Self::new() }
+}
+
+/// Mixer, render pump and device output driven together from the audio thread
+#[derive(Debug)]
+pub struct AudioEngine {
+    output: AudioOutput,
+    mixer: Arc<Mutex<AudioMixer>>,
+    pump: Option<RenderPump>,
+}
+
+impl AudioEngine {
+    pub fn new(output: AudioOutput) -> Self {
+        Self { output, mixer: Arc::new(Mutex::new(AudioMixer::new())), pump: None }
+    }
+
+    /// Shared mixer that Web Audio contexts and media elements connect to
+    pub fn mixer(&self) -> Arc<Mutex<AudioMixer>> {
+        Arc::clone(&self.mixer)
+    }
+
+    pub fn output(&self) -> &AudioOutput { &self.output }
+
+    pub fn devices(&self) -> Vec<AudioDevice> { self.output.devices() }
+
+    /// Open the device and start rendering the mixer into it
+    pub fn start(&mut self, device_id: Option<&str>, config: OutputConfig) -> OutputResult<()> {
+        let producer = self.output.start(device_id, config)?;
+        let config = self.output.config();
+        self.pump = Some(RenderPump::new(Box::new(self.mixer()), producer, config.channels, config.sample_rate));
+        Ok(())
+    }
+
+    /// Move the stream to another device
+    pub fn select_device(&mut self, device_id: Option<&str>) -> OutputResult<()> {
+        let config = self.output.config();
+        self.start(device_id, config)
+    }
+
+    /// One audio-thread iteration: render quanta into the ring, then feed the device.
+    /// Returns frames written to the device.
+    pub fn tick(&mut self) -> OutputResult<usize> {
+        let pump = self.pump.as_mut().ok_or(OutputError::NotOpen)?;
+        pump.fill();
+        self.output.pump()
+    }
+
+    /// Seconds of audio rendered since the stream started
+    pub fn rendered_time(&self) -> f64 {
+        self.pump.as_ref().map(|p| p.rendered_time()).unwrap_or(0.0)
+    }
+
+    pub fn stop(&mut self) {
+        self.pump = None;
+        self.output.stop();
+    }
+
+    pub fn is_running(&self) -> bool { self.pump.is_some() && self.output.is_running() }
+}
+
+impl Default for AudioEngine {
+    fn default() -> Self { Self::new(AudioOutput::new()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_output_stream() {
+        let mut output = AudioOutput::with_backend(Box::new(NullOutput::new()));
+        assert_eq!(output.devices().len(), 1);
+
+        let config = OutputConfig { sample_rate: 48000, channels: 2, period_frames: 256 };
+        let mut producer = output.start(None, config).unwrap();
+        assert_eq!(producer.capacity(), 256 * 2 * 4);
+
+        producer.push(&[0.0; 256 * 2]);
+        assert_eq!(output.pump().unwrap(), 256);
+    }
+
+    #[test]
+    fn test_unknown_device_rejected() {
+        let mut output = AudioOutput::with_backend(Box::new(NullOutput::new()));
+        let err = output.start(Some("hw:9,9"), OutputConfig::default()).unwrap_err();
+        assert!(matches!(err, OutputError::DeviceNotFound(_)));
+    }
+
+    #[test]
+    fn test_pump_to_device() {
+        let mut output = AudioOutput::with_backend(Box::new(NullOutput::new()));
+        let producer = output.start(Some("null"), OutputConfig::default()).unwrap();
+        let mut pump = RenderPump::new(Box::new(AudioMixer::new()), producer, 2, 48000);
+        assert!(pump.fill() > 0);
+        assert!(output.pump().unwrap() > 0);
+        assert_eq!(output.selected_device(), Some("null"));
+    }
+
+    #[test]
+    fn test_engine_plays_media_queue() {
+        let mut engine = AudioEngine::new(AudioOutput::with_backend(Box::new(NullOutput::new())));
+        let queue: SharedSampleQueue = Default::default();
+        engine.mixer().lock().unwrap().add_input(Box::new(Arc::clone(&queue)));
+        queue.lock().unwrap().push(&crate::decoders::AudioSamples {
+            pts: Duration::ZERO,
+            duration: Duration::ZERO,
+            sample_rate: 48000,
+            channels: 2,
+            data: vec![0.5; 200],
+        });
+
+        engine.start(None, OutputConfig::default()).unwrap();
+        assert!(engine.tick().unwrap() > 0);
+        assert_eq!(queue.lock().unwrap().buffered_frames(), 0);
+        assert_eq!(queue.lock().unwrap().frames_played(), 100);
+    }
+}
@@ -0,0 +1,399 @@
+//! Render Quantum Pump
+//!
+//! Pulls 128-frame render quanta from audio sources (Web Audio graph,
+//! media elements) and pushes them into the output ring buffer.
+
+use super::ring::RingProducer;
+use crate::decoders::AudioSamples;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Web Audio render quantum size in frames
+pub const RENDER_QUANTUM_FRAMES: usize = 128;
+
+/// Something that produces interleaved audio one quantum at a time
+pub trait AudioRenderSource: Send {
+    /// Render `output.len() / channels` frames of interleaved audio.
+    /// Output is pre-zeroed; sources add into it.
+    fn render(&mut self, output: &mut [f32], channels: u32, sample_rate: u32);
+
+    /// Whether the source currently produces sound
+    fn is_audible(&self) -> bool { true }
+}
+
+/// Sources shared between the audio thread and their owner
+impl<T: AudioRenderSource> AudioRenderSource for Arc<Mutex<T>> {
+    fn render(&mut self, output: &mut [f32], channels: u32, sample_rate: u32) {
+        if let Ok(mut source) = self.lock() {
+            source.render(output, channels, sample_rate);
+        }
+    }
+
+    fn is_audible(&self) -> bool {
+        self.lock().map(|s| s.is_audible()).unwrap_or(false)
+    }
+}
+
+/// Sample queue shared between a media pipeline and the mixer
+pub type SharedSampleQueue = Arc<Mutex<SampleQueueSource>>;
+
+/// Queue of decoded samples fed by a media element's pipeline
+#[derive(Debug, Default)]
+pub struct SampleQueueSource {
+    queue: VecDeque<f32>,
+    channels: u32,
+    sample_rate: u32,
+    /// Resampling position within the queue (in source frames)
+    position: f64,
+    frames_played: u64,
+    /// Media time covered by each pushed block, front is being played
+    segments: VecDeque<QueuedSegment>,
+    /// Frames of the front segment already played
+    segment_played: usize,
+    /// Media time reached when the queue ran dry
+    drained_at: Option<Duration>,
+}
+
+/// Media time span of one pushed block
+#[derive(Debug, Clone, Copy)]
+struct QueuedSegment {
+    pts: Duration,
+    /// Media duration, which differs from the frame count when time-stretched
+    duration: Duration,
+    frames: usize,
+}
+
+impl SampleQueueSource {
+    pub fn new() -> Self { Self::default() }
+
+    /// Append decoded samples
+    pub fn push(&mut self, samples: &AudioSamples) {
+        self.channels = samples.channels.max(1);
+        self.sample_rate = samples.sample_rate;
+        self.queue.extend(samples.data.iter().copied());
+
+        let frames = samples.data.len() / self.channels as usize;
+        if frames > 0 {
+            let duration = if samples.duration.is_zero() {
+                Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64)
+            } else {
+                samples.duration
+            };
+            self.segments.push_back(QueuedSegment { pts: samples.pts, duration, frames });
+            self.drained_at = None;
+        }
+    }
+
+    /// Drop queued audio (seek)
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.position = 0.0;
+        self.segments.clear();
+        self.segment_played = 0;
+        self.drained_at = None;
+    }
+
+    /// Media time of the audio the mixer is reading, if any was queued since
+    /// the last clear. Device latency is not included.
+    pub fn media_time(&self) -> Option<Duration> {
+        let Some(segment) = self.segments.front() else { return self.drained_at };
+        let played = (self.segment_played as f64 + self.position.fract()) / segment.frames as f64;
+        Some(segment.pts + segment.duration.mul_f64(played.min(1.0)))
+    }
+
+    /// Account for `frames` source frames leaving the queue
+    fn advance_segments(&mut self, mut frames: usize) {
+        while frames > 0 {
+            let Some(segment) = self.segments.front() else { break };
+            let remaining = segment.frames - self.segment_played;
+            if frames < remaining {
+                self.segment_played += frames;
+                break;
+            }
+            frames -= remaining;
+            self.drained_at = Some(segment.pts + segment.duration);
+            self.segments.pop_front();
+            self.segment_played = 0;
+        }
+    }
+
+    /// Buffered audio in frames
+    pub fn buffered_frames(&self) -> usize {
+        self.queue.len() / self.channels.max(1) as usize
+    }
+
+    /// Output frames played since creation
+    pub fn frames_played(&self) -> u64 { self.frames_played }
+
+    fn sample(&self, frame: usize, channel: u32) -> f32 {
+        let src_channels = self.channels.max(1);
+        // Upmix mono, drop extra channels
+        let ch = channel.min(src_channels - 1) as usize;
+        self.queue.get(frame * src_channels as usize + ch).copied().unwrap_or(0.0)
+    }
+}
+
+impl AudioRenderSource for SampleQueueSource {
+    fn render(&mut self, output: &mut [f32], channels: u32, sample_rate: u32) {
+        if self.queue.is_empty() || channels == 0 {
+            return;
+        }
+        let step = if sample_rate == 0 || self.sample_rate == 0 {
+            1.0
+        } else {
+            self.sample_rate as f64 / sample_rate as f64
+        };
+
+        // Linear-interpolating resampler
+        for frame in output.chunks_mut(channels as usize) {
+            let idx = self.position.floor() as usize;
+            if idx >= self.buffered_frames() {
+                break;
+            }
+            let frac = (self.position - idx as f64) as f32;
+            for (ch, out) in frame.iter_mut().enumerate() {
+                let a = self.sample(idx, ch as u32);
+                let b = if idx + 1 < self.buffered_frames() { self.sample(idx + 1, ch as u32) } else { a };
+                *out += a + (b - a) * frac;
+            }
+            self.position += step;
+            self.frames_played += 1;
+        }
+
+        // Release fully consumed source frames
+        let consumed = (self.position.floor() as usize).min(self.buffered_frames());
+        self.queue.drain(..consumed * self.channels.max(1) as usize);
+        self.position -= consumed as f64;
+        self.advance_segments(consumed);
+    }
+
+    fn is_audible(&self) -> bool {
+        !self.queue.is_empty()
+    }
+}
+
+/// Mixer input handle
+pub type MixerInputId = u32;
+
+struct MixerInput {
+    id: MixerInputId,
+    source: Box<dyn AudioRenderSource>,
+    gain: f32,
+    muted: bool,
+}
+
+/// Sums several sources into one output
+pub struct AudioMixer {
+    inputs: Vec<MixerInput>,
+    next_id: MixerInputId,
+    scratch: Vec<f32>,
+    master_gain: f32,
+}
+
+impl std::fmt::Debug for AudioMixer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioMixer").field("inputs", &self.inputs.len()).finish()
+    }
+}
+
+impl AudioMixer {
+    pub fn new() -> Self {
+        Self { inputs: Vec::new(), next_id: 0, scratch: Vec::new(), master_gain: 1.0 }
+    }
+
+    /// Add a source, returning its input handle
+    pub fn add_input(&mut self, source: Box<dyn AudioRenderSource>) -> MixerInputId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.inputs.push(MixerInput { id, source, gain: 1.0, muted: false });
+        id
+    }
+
+    pub fn remove_input(&mut self, id: MixerInputId) -> Option<Box<dyn AudioRenderSource>> {
+        let pos = self.inputs.iter().position(|i| i.id == id)?;
+        Some(self.inputs.remove(pos).source)
+    }
+
+    pub fn set_gain(&mut self, id: MixerInputId, gain: f32) {
+        if let Some(input) = self.inputs.iter_mut().find(|i| i.id == id) {
+            input.gain = gain.max(0.0);
+        }
+    }
+
+    pub fn set_muted(&mut self, id: MixerInputId, muted: bool) {
+        if let Some(input) = self.inputs.iter_mut().find(|i| i.id == id) {
+            input.muted = muted;
+        }
+    }
+
+    pub fn set_master_gain(&mut self, gain: f32) {
+        self.master_gain = gain.max(0.0);
+    }
+
+    pub fn input_count(&self) -> usize { self.inputs.len() }
+}
+
+impl Default for AudioMixer {
+    fn default() -> Self { Self::new() }
+}
+
+impl AudioRenderSource for AudioMixer {
+    fn render(&mut self, output: &mut [f32], channels: u32, sample_rate: u32) {
+        self.scratch.resize(output.len(), 0.0);
+        for input in &mut self.inputs {
+            self.scratch.fill(0.0);
+            // Muted inputs still advance so they stay in sync
+            input.source.render(&mut self.scratch, channels, sample_rate);
+            if input.muted {
+                continue;
+            }
+            for (out, s) in output.iter_mut().zip(&self.scratch) {
+                *out += s * input.gain;
+            }
+        }
+        if self.master_gain != 1.0 {
+            output.iter_mut().for_each(|s| *s *= self.master_gain);
+        }
+    }
+
+    fn is_audible(&self) -> bool {
+        self.master_gain > 0.0 && self.inputs.iter().any(|i| !i.muted && i.gain > 0.0 && i.source.is_audible())
+    }
+}
+
+/// Renders quanta from a source into the ring buffer until it is full
+pub struct RenderPump {
+    source: Box<dyn AudioRenderSource>,
+    producer: RingProducer,
+    channels: u32,
+    sample_rate: u32,
+    quantum: Vec<f32>,
+    quanta_rendered: u64,
+}
+
+impl std::fmt::Debug for RenderPump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RenderPump")
+            .field("channels", &self.channels)
+            .field("sample_rate", &self.sample_rate)
+            .field("quanta_rendered", &self.quanta_rendered)
+            .finish()
+    }
+}
+
+impl RenderPump {
+    pub fn new(source: Box<dyn AudioRenderSource>, producer: RingProducer, channels: u32, sample_rate: u32) -> Self {
+        Self {
+            source,
+            producer,
+            channels,
+            sample_rate,
+            quantum: vec![0.0; RENDER_QUANTUM_FRAMES * channels as usize],
+            quanta_rendered: 0,
+        }
+    }
+
+    /// Render as many whole quanta as fit in the ring; returns quanta rendered
+    pub fn fill(&mut self) -> usize {
+        let mut rendered = 0;
+        if self.quantum.is_empty() {
+            return 0;
+        }
+        while self.producer.free() >= self.quantum.len() {
+            self.quantum.fill(0.0);
+            self.source.render(&mut self.quantum, self.channels, self.sample_rate);
+            for s in &mut self.quantum {
+                *s = s.clamp(-1.0, 1.0);
+            }
+            self.producer.push(&self.quantum);
+            rendered += 1;
+        }
+        self.quanta_rendered += rendered as u64;
+        rendered
+    }
+
+    pub fn source_mut(&mut self) -> &mut dyn AudioRenderSource { self.source.as_mut() }
+    pub fn quanta_rendered(&self) -> u64 { self.quanta_rendered }
+
+    /// Time rendered so far at the pump's sample rate
+    pub fn rendered_time(&self) -> f64 {
+        (self.quanta_rendered * RENDER_QUANTUM_FRAMES as u64) as f64 / self.sample_rate.max(1) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ring::audio_ring_buffer;
+
+    struct Constant(f32);
+    impl AudioRenderSource for Constant {
+        fn render(&mut self, output: &mut [f32], _channels: u32, _sample_rate: u32) {
+            output.iter_mut().for_each(|s| *s += self.0);
+        }
+    }
+
+    #[test]
+    fn test_mixer_sums_and_mutes() {
+        let mut mixer = AudioMixer::new();
+        let a = mixer.add_input(Box::new(Constant(0.25)));
+        mixer.add_input(Box::new(Constant(0.5)));
+        let mut out = [0.0; 4];
+        mixer.render(&mut out, 2, 48000);
+        assert_eq!(out, [0.75; 4]);
+
+        mixer.set_muted(a, true);
+        let mut out = [0.0; 4];
+        mixer.render(&mut out, 2, 48000);
+        assert_eq!(out, [0.5; 4]);
+    }
+
+    #[test]
+    fn test_pump_fills_whole_quanta() {
+        let (tx, rx) = audio_ring_buffer(RENDER_QUANTUM_FRAMES * 2 * 3 + 10);
+        let mut pump = RenderPump::new(Box::new(Constant(2.0)), tx, 2, 48000);
+        assert_eq!(pump.fill(), 3);
+        assert_eq!(rx.available(), RENDER_QUANTUM_FRAMES * 2 * 3);
+        // Output is clamped
+        assert_eq!(pump.fill(), 0);
+    }
+
+    #[test]
+    fn test_sample_queue_upmix() {
+        let mut src = SampleQueueSource::new();
+        src.push(&AudioSamples {
+            pts: Duration::ZERO,
+            duration: Duration::ZERO,
+            sample_rate: 48000,
+            channels: 1,
+            data: vec![0.1, 0.2],
+        });
+        let mut out = [0.0; 4];
+        src.render(&mut out, 2, 48000);
+        assert_eq!(out, [0.1, 0.1, 0.2, 0.2]);
+        assert_eq!(src.buffered_frames(), 0);
+    }
+
+    #[test]
+    fn test_sample_queue_media_time() {
+        let mut src = SampleQueueSource::new();
+        assert_eq!(src.media_time(), None);
+        // 100 ms of mono audio stretched to cover 200 ms of media
+        src.push(&AudioSamples {
+            pts: Duration::from_secs(1),
+            duration: Duration::from_millis(200),
+            sample_rate: 1000,
+            channels: 1,
+            data: vec![0.0; 100],
+        });
+        let mut out = [0.0; 50];
+        src.render(&mut out, 1, 1000);
+        assert_eq!(src.media_time(), Some(Duration::from_millis(1100)));
+        let mut out = [0.0; 80];
+        src.render(&mut out, 1, 1000);
+        assert_eq!(src.media_time(), Some(Duration::from_millis(1200)));
+        src.clear();
+        assert_eq!(src.media_time(), None);
+    }
+}
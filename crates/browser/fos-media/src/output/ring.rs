@@ -0,0 +1,160 @@
+//! Audio Ring Buffer
+//!
+//! Lock-free single-producer/single-consumer ring of interleaved f32 samples.
+//! The render pump writes on the audio thread, the device callback reads.
+
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+struct Shared {
+    data: Box<[UnsafeCell<f32>]>,
+    /// Total samples written (monotonic)
+    write: AtomicUsize,
+    /// Total samples read (monotonic)
+    read: AtomicUsize,
+}
+
+// SAFETY: the producer only writes slots in [read + capacity, write) and the consumer
+// only reads slots in [read, write); the atomics order the hand-off between them.
+unsafe impl Sync for Shared {}
+unsafe impl Send for Shared {}
+
+impl Shared {
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+/// Create a ring buffer holding `capacity` samples
+pub fn audio_ring_buffer(capacity: usize) -> (RingProducer, RingConsumer) {
+    let capacity = capacity.max(1);
+    let data = (0..capacity).map(|_| UnsafeCell::new(0.0)).collect::<Vec<_>>().into_boxed_slice();
+    let shared = Arc::new(Shared { data, write: AtomicUsize::new(0), read: AtomicUsize::new(0) });
+    (RingProducer { shared: Arc::clone(&shared) }, RingConsumer { shared, underruns: 0 })
+}
+
+/// Writing half of the ring
+pub struct RingProducer {
+    shared: Arc<Shared>,
+}
+
+impl std::fmt::Debug for RingProducer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RingProducer").field("free", &self.free()).finish()
+    }
+}
+
+impl RingProducer {
+    /// Samples that can be written without overwriting unread data
+    pub fn free(&self) -> usize {
+        let write = self.shared.write.load(Ordering::Relaxed);
+        let read = self.shared.read.load(Ordering::Acquire);
+        self.shared.capacity() - (write - read)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+
+    /// Write as many samples as fit, returning the count written
+    pub fn push(&mut self, samples: &[f32]) -> usize {
+        let n = samples.len().min(self.free());
+        let cap = self.shared.capacity();
+        let write = self.shared.write.load(Ordering::Relaxed);
+        for (i, &s) in samples[..n].iter().enumerate() {
+            // SAFETY: slot is outside the consumer's readable window (see `Shared`)
+            unsafe { *self.shared.data[(write + i) % cap].get() = s; }
+        }
+        self.shared.write.store(write + n, Ordering::Release);
+        n
+    }
+}
+
+/// Reading half of the ring
+pub struct RingConsumer {
+    shared: Arc<Shared>,
+    underruns: u64,
+}
+
+impl std::fmt::Debug for RingConsumer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RingConsumer")
+            .field("available", &self.available())
+            .field("underruns", &self.underruns)
+            .finish()
+    }
+}
+
+impl RingConsumer {
+    /// Samples ready to read
+    pub fn available(&self) -> usize {
+        let write = self.shared.write.load(Ordering::Acquire);
+        let read = self.shared.read.load(Ordering::Relaxed);
+        write - read
+    }
+
+    /// Read into `out`, returning the count read
+    pub fn pop(&mut self, out: &mut [f32]) -> usize {
+        let n = out.len().min(self.available());
+        let cap = self.shared.capacity();
+        let read = self.shared.read.load(Ordering::Relaxed);
+        for (i, slot) in out[..n].iter_mut().enumerate() {
+            // SAFETY: slot is inside the readable window published by the producer
+            *slot = unsafe { *self.shared.data[(read + i) % cap].get() };
+        }
+        self.shared.read.store(read + n, Ordering::Release);
+        n
+    }
+
+    /// Fill `out` completely, padding with silence on underrun
+    pub fn pop_or_silence(&mut self, out: &mut [f32]) -> usize {
+        let n = self.pop(out);
+        if n < out.len() {
+            out[n..].fill(0.0);
+            self.underruns += 1;
+        }
+        n
+    }
+
+    /// Number of reads that had to be padded with silence
+    pub fn underruns(&self) -> u64 {
+        self.underruns
+    }
+
+    /// Discard all buffered samples (e.g. on seek or device switch)
+    pub fn clear(&mut self) {
+        let write = self.shared.write.load(Ordering::Acquire);
+        self.shared.read.store(write, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_pop_wraps() {
+        let (mut tx, mut rx) = audio_ring_buffer(4);
+        assert_eq!(tx.push(&[1.0, 2.0, 3.0]), 3);
+        let mut out = [0.0; 2];
+        assert_eq!(rx.pop(&mut out), 2);
+        assert_eq!(out, [1.0, 2.0]);
+
+        // Wraps around the end of storage
+        assert_eq!(tx.push(&[4.0, 5.0, 6.0, 7.0]), 3);
+        let mut out = [0.0; 4];
+        assert_eq!(rx.pop(&mut out), 4);
+        assert_eq!(out, [3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn test_underrun_pads_silence() {
+        let (mut tx, mut rx) = audio_ring_buffer(8);
+        tx.push(&[0.5]);
+        let mut out = [1.0; 3];
+        assert_eq!(rx.pop_or_silence(&mut out), 1);
+        assert_eq!(out, [0.5, 0.0, 0.0]);
+        assert_eq!(rx.underruns(), 1);
+    }
+}
@@ -0,0 +1,54 @@
+//! WASAPI Output (Windows)
+//!
+//! This is a stub implementation. Full implementation would use
+//! IMMDeviceEnumerator, IAudioClient (shared mode, event driven) and
+//! IAudioRenderClient.
+
+use super::{AudioDevice, AudioOutputBackend, NullOutput, OutputConfig, OutputResult};
+use std::time::Duration;
+
+/// WASAPI shared-mode render client
+#[derive(Debug, Default)]
+pub struct WasapiOutput {
+    // In real impl: IAudioClient, IAudioRenderClient, event HANDLE
+    sink: NullOutput,
+}
+
+impl WasapiOutput {
+    pub fn new() -> Self { Self::default() }
+}
+
+impl AudioOutputBackend for WasapiOutput {
+    fn name(&self) -> &'static str { "wasapi" }
+
+    fn enumerate_devices(&self) -> Vec<AudioDevice> {
+        // Real impl: IMMDeviceEnumerator::EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE)
+        vec![AudioDevice {
+            id: "default".into(),
+            name: "Default Output".into(),
+            channels: 2,
+            sample_rates: vec![44100, 48000],
+            is_default: true,
+        }]
+    }
+
+    fn open(&mut self, device_id: Option<&str>, config: OutputConfig) -> OutputResult<OutputConfig> {
+        // Real impl: IAudioClient::Initialize(AUDCLNT_SHAREMODE_SHARED,
+        // AUDCLNT_STREAMFLAGS_EVENTCALLBACK) using the mix format's rate
+        self.sink.open(device_id, config)
+    }
+
+    fn writable_frames(&self) -> usize {
+        // Real impl: buffer size - GetCurrentPadding
+        self.sink.writable_frames()
+    }
+
+    fn write(&mut self, samples: &[f32]) -> OutputResult<usize> {
+        // Real impl: GetBuffer / ReleaseBuffer
+        self.sink.write(samples)
+    }
+
+    fn latency(&self) -> Duration { self.sink.latency() }
+    fn close(&mut self) { self.sink.close(); }
+    fn is_open(&self) -> bool { self.sink.is_open() }
+}
@@ -1,9 +1,53 @@
 //! Media Clock
 //!
 //! Precise timing for audio/video synchronization.
+//!
+//! The clock runs from the wall clock, and while audio is playing it is slaved
+//! to the audio device: the media time the mixer is reading, minus output
+//! latency, is the master and the wall clock only interpolates between device
+//! callbacks. When audio stalls (underrun, muted rate) the wall clock carries on.
+//!
+//! Small drift from the audio clock is slewed away by running slightly fast or
+//! slow, so video does not stutter; only large drift makes the clock jump.
 
+use crate::output::SharedSampleQueue;
 use std::time::{Duration, Instant};
 
+/// Lowest playback rate the clock runs at
+pub const MIN_PLAYBACK_RATE: f64 = 0.0625;
+/// Highest playback rate the clock runs at
+pub const MAX_PLAYBACK_RATE: f64 = 16.0;
+/// Drift from the audio clock that triggers a resync
+const RESYNC_THRESHOLD: Duration = Duration::from_millis(100);
+/// Drift below this is left alone
+const SLEW_THRESHOLD: Duration = Duration::from_millis(5);
+/// Largest relative rate change used to slew toward the audio clock
+const MAX_SLEW: f64 = 0.05;
+/// Drift is worked off over this much wall time, within `MAX_SLEW`
+const SLEW_PERIOD: f64 = 0.5;
+/// Audio time not advancing for this long means audio stopped driving the clock
+const AUDIO_STALL: Duration = Duration::from_millis(200);
+
+/// Clock driving media time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockSource {
+    /// The audio device's playback position
+    Audio,
+    /// The wall clock
+    System,
+}
+
+/// Audio output the clock follows
+#[derive(Debug)]
+struct AudioMaster {
+    queue: SharedSampleQueue,
+    latency: Duration,
+    /// False while audio is not played out (muted rate)
+    active: bool,
+    /// Last media time read from the queue and when it changed
+    last: Option<(Duration, Instant)>,
+}
+
 /// Media clock for A/V sync
 #[derive(Debug)]
 pub struct MediaClock {
@@ -11,11 +55,104 @@ pub struct MediaClock {
     pause_time: Option<Instant>,
     base_position: Duration,
     playback_rate: f64,
+    /// Relative rate adjustment slewing toward the audio clock
+    correction: f64,
+    /// Rate before the last change and when it changed; audio still in the
+    /// output latency was rendered at that rate
+    rate_change: Option<(f64, Instant)>,
+    /// Audio clock minus this clock at the last sync, in seconds
+    drift: f64,
+    audio: Option<AudioMaster>,
 }
 
 impl MediaClock {
     pub fn new() -> Self {
-        Self { start_time: None, pause_time: None, base_position: Duration::ZERO, playback_rate: 1.0 }
+        Self {
+            start_time: None, pause_time: None, base_position: Duration::ZERO, playback_rate: 1.0,
+            correction: 0.0, rate_change: None, drift: 0.0, audio: None,
+        }
+    }
+    
+    /// Follow the media time of an audio output queue
+    pub fn set_audio_master(&mut self, queue: SharedSampleQueue, latency: Duration) {
+        self.audio = Some(AudioMaster { queue, latency, active: true, last: None });
+    }
+    
+    pub fn clear_audio_master(&mut self) {
+        self.audio = None;
+        self.set_correction(0.0);
+    }
+    
+    /// Let audio drive the clock or not; inactive audio (muted rates) leaves
+    /// the wall clock in charge straight away instead of after a stall
+    pub fn set_audio_active(&mut self, active: bool) {
+        let Some(audio) = self.audio.as_mut() else { return };
+        if audio.active == active { return; }
+        audio.active = active;
+        audio.last = None;
+        self.set_correction(0.0);
+    }
+    
+    /// Whether the audio device is currently driving the clock
+    pub fn is_audio_driven(&self) -> bool {
+        self.audio.as_ref()
+            .filter(|a| a.active)
+            .and_then(|a| a.last)
+            .is_some_and(|(_, at)| at.elapsed() < AUDIO_STALL)
+    }
+    
+    /// Clock currently driving media time
+    pub fn source(&self) -> ClockSource {
+        if self.is_audio_driven() { ClockSource::Audio } else { ClockSource::System }
+    }
+    
+    /// Audio clock minus this clock at the last sync, in seconds
+    pub fn drift(&self) -> f64 { self.drift }
+    
+    /// Follow the audio device clock: slew toward it when it drifted a little
+    /// from the wall clock, jump to it when it drifted a lot
+    pub fn sync(&mut self) {
+        if !self.is_running() { return; }
+        let Some(audio) = self.audio.as_mut().filter(|a| a.active) else { return };
+        let Some(time) = audio.queue.lock().ok().and_then(|q| q.media_time()) else { return };
+        if audio.last.is_some_and(|(last, _)| last == time) { return; }
+        audio.last = Some((time, Instant::now()));
+        let latency = audio.latency;
+        
+        // Audio reaching the speaker now was queued `latency` of device time
+        // ago, at the rate in effect back then
+        let latency_rate = match self.rate_change {
+            Some((previous, at)) if at.elapsed() < latency => previous,
+            _ => self.playback_rate,
+        };
+        let audible = time.saturating_sub(latency.mul_f64(latency_rate));
+        self.drift = audible.as_secs_f64() - self.position().as_secs_f64();
+        
+        if self.drift.abs() > RESYNC_THRESHOLD.as_secs_f64() {
+            self.base_position = audible;
+            self.start_time = Some(Instant::now());
+            self.correction = 0.0;
+        } else if self.drift.abs() < SLEW_THRESHOLD.as_secs_f64() {
+            self.set_correction(0.0);
+        } else {
+            self.set_correction((self.drift / (SLEW_PERIOD * self.playback_rate)).clamp(-MAX_SLEW, MAX_SLEW));
+        }
+    }
+    
+    fn set_correction(&mut self, correction: f64) {
+        if correction != self.correction {
+            self.rebase();
+            self.correction = correction;
+        }
+    }
+    
+    /// Restart elapsed time from the current position
+    fn rebase(&mut self) {
+        let now = Instant::now();
+        let running = self.is_running();
+        self.base_position = self.position();
+        self.start_time = Some(now);
+        self.pause_time = if running { None } else { Some(now) };
     }
     
     pub fn start(&mut self) {
@@ -30,30 +167,37 @@ impl MediaClock {
     
     pub fn pause(&mut self) { self.pause_time = Some(Instant::now()); }
     
+    /// Jump to a position, staying paused if the clock was not running
     pub fn seek(&mut self, position: Duration) {
+        let now = Instant::now();
+        let running = self.is_running();
         self.base_position = position;
-        self.start_time = Some(Instant::now());
-        self.pause_time = None;
+        self.start_time = Some(now);
+        self.pause_time = if running { None } else { Some(now) };
+        self.correction = 0.0;
+        self.drift = 0.0;
+        if let Some(audio) = self.audio.as_mut() { audio.last = None; }
     }
     
     pub fn position(&self) -> Duration {
-        match (self.start_time, self.pause_time) {
-            (Some(start), None) => {
-                let elapsed = start.elapsed();
-                self.base_position + Duration::from_secs_f64(elapsed.as_secs_f64() * self.playback_rate)
-            }
-            (Some(start), Some(pause)) => {
-                let elapsed = pause.duration_since(start);
-                self.base_position + Duration::from_secs_f64(elapsed.as_secs_f64() * self.playback_rate)
-            }
-            _ => self.base_position,
-        }
+        let elapsed = match (self.start_time, self.pause_time) {
+            (Some(start), None) => start.elapsed(),
+            (Some(start), Some(pause)) => pause.duration_since(start),
+            _ => return self.base_position,
+        };
+        let rate = self.playback_rate * (1.0 + self.correction);
+        self.base_position + Duration::from_secs_f64(elapsed.as_secs_f64() * rate)
     }
     
+    /// Change the rate without moving the position
     pub fn set_playback_rate(&mut self, rate: f64) {
-        self.base_position = self.position();
-        self.start_time = Some(Instant::now());
-        self.playback_rate = rate.clamp(0.25, 4.0);
+        let rate = rate.clamp(MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE);
+        self.rebase();
+        if rate != self.playback_rate {
+            self.rate_change = Some((self.playback_rate, Instant::now()));
+        }
+        self.playback_rate = rate;
+        self.correction = 0.0;
     }
     
     pub fn playback_rate(&self) -> f64 { self.playback_rate }
@@ -67,4 +211,71 @@ mod tests {
     use super::*;
     #[test]
     fn test_clock() { let c = MediaClock::new(); assert_eq!(c.position(), Duration::ZERO); assert!(!c.is_running()); }
+    
+    #[test]
+    fn test_slaves_to_audio_clock() {
+        use crate::decoders::AudioSamples;
+        use crate::output::{AudioRenderSource, SampleQueueSource};
+        use std::sync::{Arc, Mutex};
+        
+        let queue = Arc::new(Mutex::new(SampleQueueSource::new()));
+        let mut c = MediaClock::new();
+        c.set_audio_master(queue.clone(), Duration::from_millis(20));
+        c.start();
+        c.sync();
+        assert!(!c.is_audio_driven());
+        
+        // Device has played 500 ms into a block starting at 5 s
+        queue.lock().unwrap().push(&AudioSamples {
+            pts: Duration::from_secs(5), duration: Duration::ZERO, sample_rate: 1000, channels: 1, data: vec![0.0; 1000],
+        });
+        let mut out = vec![0.0; 500];
+        queue.lock().unwrap().render(&mut out, 1, 1000);
+        c.sync();
+        assert!(c.is_audio_driven());
+        let pos = c.position();
+        assert!(pos >= Duration::from_millis(480) + Duration::from_secs(4) && pos < Duration::from_millis(5500), "{:?}", pos);
+        
+        c.set_playback_rate(100.0);
+        assert_eq!(c.playback_rate(), MAX_PLAYBACK_RATE);
+    }
+    
+    #[test]
+    fn test_slews_small_drift() {
+        use crate::decoders::AudioSamples;
+        use crate::output::{AudioRenderSource, SampleQueueSource};
+        use std::sync::{Arc, Mutex};
+        
+        let queue = Arc::new(Mutex::new(SampleQueueSource::new()));
+        let mut c = MediaClock::new();
+        c.set_audio_master(queue.clone(), Duration::ZERO);
+        c.start();
+        assert_eq!(c.source(), ClockSource::System);
+        
+        // Audio runs 60 ms ahead: slewed, not jumped to
+        queue.lock().unwrap().push(&AudioSamples {
+            pts: Duration::ZERO, duration: Duration::from_secs(1), sample_rate: 1000, channels: 1, data: vec![0.0; 1000],
+        });
+        queue.lock().unwrap().render(&mut vec![0.0; 60], 1, 1000);
+        c.sync();
+        assert_eq!(c.source(), ClockSource::Audio);
+        assert!(c.drift() > 0.04, "{}", c.drift());
+        assert!(c.position() < Duration::from_millis(50));
+        assert_eq!(c.correction, MAX_SLEW);
+        
+        // Rate changes keep the position
+        let before = c.position();
+        c.set_playback_rate(2.0);
+        assert!(c.position().abs_diff(before) < Duration::from_millis(5));
+        assert_eq!(c.correction, 0.0);
+        
+        // Far off: jump
+        queue.lock().unwrap().render(&mut vec![0.0; 400], 1, 1000);
+        c.sync();
+        assert!(c.position() >= Duration::from_millis(450));
+        
+        // Muted audio hands the clock to the wall clock
+        c.set_audio_active(false);
+        assert_eq!(c.source(), ClockSource::System);
+    }
 }
@@ -5,24 +5,71 @@
 pub mod clock;
 pub mod renderer;
 pub mod low_latency;
+pub mod wsola;
 
-use crate::containers::{Demuxer, Packet, TrackType};
+use crate::containers::{Demuxer, InitData, Packet, TrackType};
+use crate::eme::{EmeError, SharedMediaKeys};
 use crate::decoders::{VideoFrame, AudioSamples, EncodedPacket};
 use crate::decoders::hw::{DecoderBackend, HwCodec};
 use crate::decoders::audio::aac::AacDecoder;
 use crate::decoders::audio::vorbis::VorbisDecoder;
+use crate::decoders::audio::opus::OpusDecoder;
+use crate::decoders::audio::flac::FlacDecoder;
 use crate::decoders::AudioDecoderTrait;
+use crate::codecs::CodecType;
 use clock::MediaClock;
 use renderer::{VideoRenderer, AudioRenderer};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Interval between `timeupdate` events during playback (spec: 15 to 250 ms)
+pub const TIMEUPDATE_INTERVAL: Duration = Duration::from_millis(250);
+/// How far ahead of the clock decoded audio is handed to the output
+const AUDIO_LEAD: Duration = Duration::from_millis(200);
 
 /// Media pipeline state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PipelineState { Idle, Buffering, Playing, Paused, Ended, Error }
 
+/// Events for the media element to fire
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipelineEvent {
+    /// `timeupdate` with the current position
+    TimeUpdate(Duration),
+    /// `ratechange`
+    RateChange(f64),
+    Seeked(Duration),
+    Ended,
+    /// `encrypted` with init data found by the demuxer
+    Encrypted(InitData),
+    /// `waitingforkey`: an encrypted sample has no usable key yet
+    WaitingForKey,
+}
+
 /// Audio decoder backend
 #[derive(Debug)]
-pub enum AudioDecoderBackend { Aac(AacDecoder), Vorbis(VorbisDecoder) }
+pub enum AudioDecoderBackend { Aac(AacDecoder), Vorbis(Box<VorbisDecoder>), Opus(OpusDecoder), Flac(FlacDecoder) }
+
+impl AudioDecoderBackend {
+    /// Software decoder for `codec`, if there is one
+    pub fn for_codec(codec: CodecType) -> Option<Self> {
+        Some(match codec {
+            CodecType::AAC => Self::Aac(AacDecoder::new()),
+            CodecType::Vorbis => Self::Vorbis(Box::default()),
+            CodecType::Opus => Self::Opus(OpusDecoder::new()),
+            CodecType::FLAC => Self::Flac(FlacDecoder::new()),
+            _ => return None,
+        })
+    }
+    
+    pub fn decoder(&mut self) -> &mut dyn AudioDecoderTrait {
+        match self {
+            Self::Aac(d) => d,
+            Self::Vorbis(d) => d.as_mut(),
+            Self::Opus(d) => d,
+            Self::Flac(d) => d,
+        }
+    }
+}
 
 /// Media pipeline
 pub struct MediaPipeline {
@@ -35,6 +82,22 @@ pub struct MediaPipeline {
     state: PipelineState,
     video_queue: Vec<VideoFrame>,
     audio_queue: Vec<AudioSamples>,
+    /// Decoded video before this position is discarded after a seek
+    seek_target: Option<Duration>,
+    /// Demuxer reached the end; remaining queued media is still presented
+    end_of_stream: bool,
+    preserves_pitch: bool,
+    /// Late video frames skipped without presenting
+    frames_dropped: u64,
+    last_time_update: Option<Instant>,
+    events: Vec<PipelineEvent>,
+    media_keys: Option<SharedMediaKeys>,
+    /// Encrypted sample held back until its key arrives
+    pending_encrypted: Option<Packet>,
+    /// `waitingforkey` was fired for the pending sample
+    waiting_for_key: bool,
+    /// Demuxer init data already announced with `encrypted`
+    init_data_seen: usize,
 }
 
 impl std::fmt::Debug for MediaPipeline {
@@ -54,15 +117,27 @@ impl MediaPipeline {
             _ => HwCodec::H264,
         });
         
-        let audio_codec = demuxer.audio_track().map(|t| match t.codec {
-            crate::containers::CodecId::Aac => AudioDecoderBackend::Aac(AacDecoder::new()),
-            crate::containers::CodecId::Vorbis => AudioDecoderBackend::Vorbis(VorbisDecoder::new()),
-            _ => AudioDecoderBackend::Aac(AacDecoder::new()),
+        let mut audio_codec = demuxer.audio_track().map(|t| {
+            crate::containers::codec_type(t.codec)
+                .and_then(AudioDecoderBackend::for_codec)
+                .unwrap_or_else(|| AudioDecoderBackend::Aac(AacDecoder::new()))
         });
+        if let (Some(dec), Some(description)) = (audio_codec.as_mut(), demuxer.audio_track().and_then(|t| t.description())) {
+            // Without a usable configuration, in-band headers may still set it up
+            let _ = dec.decoder().configure(&description);
+        }
+        
+        let mut video_decoder = video_codec.map(DecoderBackend::new);
+        if let (Some(dec), Some(track)) = (video_decoder.as_mut(), demuxer.video_track()) {
+            if !track.codec_private.is_empty() {
+                // A bad configuration record surfaces again as decode errors
+                let _ = dec.configure(&track.codec_private);
+            }
+        }
         
         Self {
             demuxer,
-            video_decoder: video_codec.map(DecoderBackend::new),
+            video_decoder,
             audio_decoder: audio_codec,
             video_renderer: VideoRenderer::new(),
             audio_renderer: AudioRenderer::new(),
@@ -70,34 +145,151 @@ impl MediaPipeline {
             state: PipelineState::Idle,
             video_queue: Vec::new(),
             audio_queue: Vec::new(),
+            seek_target: None,
+            end_of_stream: false,
+            preserves_pitch: true,
+            frames_dropped: 0,
+            last_time_update: None,
+            events: Vec::new(),
+            media_keys: None,
+            pending_encrypted: None,
+            waiting_for_key: false,
+            init_data_seen: 0,
         }
     }
     
+    /// `setMediaKeys()`: keys used to decrypt protected samples
+    pub fn set_media_keys(&mut self, media_keys: Option<SharedMediaKeys>) {
+        self.media_keys = media_keys;
+    }
+    
+    /// Whether playback is blocked on a missing key
+    pub fn is_waiting_for_key(&self) -> bool { self.pending_encrypted.is_some() }
+    
+    /// Queue `encrypted` for init data the demuxer found since the last check
+    fn announce_init_data(&mut self) {
+        let init_data = self.demuxer.init_data();
+        for data in &init_data[self.init_data_seen.min(init_data.len())..] {
+            self.events.push(PipelineEvent::Encrypted(data.clone()));
+        }
+        self.init_data_seen = init_data.len();
+    }
+    
     pub fn state(&self) -> PipelineState { self.state }
     pub fn duration(&self) -> Option<Duration> { self.demuxer.duration() }
     pub fn position(&self) -> Duration { self.clock.position() }
     
-    pub fn play(&mut self) { self.state = PipelineState::Playing; self.clock.start(); }
-    pub fn pause(&mut self) { self.state = PipelineState::Paused; self.clock.pause(); }
+    /// Send decoded audio to an output mixer input; the clock then follows
+    /// the audio device
+    pub fn connect_audio_output(&mut self, queue: crate::output::SharedSampleQueue) {
+        self.clock.set_audio_master(queue.clone(), self.audio_renderer.latency());
+        self.audio_renderer.connect_output(queue);
+    }
+    
+    pub fn play(&mut self) {
+        self.state = PipelineState::Playing;
+        self.clock.start();
+        self.last_time_update = Some(Instant::now());
+    }
+    
+    pub fn pause(&mut self) {
+        if self.state == PipelineState::Playing { self.time_update(); }
+        self.state = PipelineState::Paused;
+        self.clock.pause();
+    }
+    
+    pub fn playback_rate(&self) -> f64 { self.clock.playback_rate() }
+    
+    /// Change `playbackRate`; audio is time-stretched, resampled or muted
+    /// depending on the rate and `preservesPitch`
+    pub fn set_playback_rate(&mut self, rate: f64) {
+        let previous = self.clock.playback_rate();
+        self.clock.set_playback_rate(rate);
+        let rate = self.clock.playback_rate();
+        self.audio_renderer.set_playback_rate(rate, self.preserves_pitch);
+        self.clock.set_audio_active(self.audio_renderer.rate_mode() != renderer::AudioRateMode::Muted);
+        if rate != previous { self.events.push(PipelineEvent::RateChange(rate)); }
+    }
+    
+    pub fn preserves_pitch(&self) -> bool { self.preserves_pitch }
+    
+    pub fn set_preserves_pitch(&mut self, preserves_pitch: bool) {
+        self.preserves_pitch = preserves_pitch;
+        self.audio_renderer.set_playback_rate(self.clock.playback_rate(), preserves_pitch);
+    }
+    
+    pub fn audio_rate_mode(&self) -> renderer::AudioRateMode { self.audio_renderer.rate_mode() }
+    /// Clock currently driving playback: the audio device or the wall clock
+    pub fn clock_source(&self) -> clock::ClockSource { self.clock.source() }
+    pub fn frames_dropped(&self) -> u64 { self.frames_dropped }
+    
+    /// Events raised since the last call
+    pub fn take_events(&mut self) -> Vec<PipelineEvent> { std::mem::take(&mut self.events) }
+    
+    fn time_update(&mut self) {
+        self.events.push(PipelineEvent::TimeUpdate(self.clock.position()));
+        self.last_time_update = Some(Instant::now());
+    }
     
     pub fn seek(&mut self, position: Duration) -> Result<(), &'static str> {
         self.demuxer.seek(position).map_err(|_| "Seek failed")?;
+        self.pending_encrypted = None;
+        self.waiting_for_key = false;
         self.video_queue.clear();
         self.audio_queue.clear();
+        self.audio_renderer.flush();
+        if let Some(dec) = self.video_decoder.as_mut() { dec.reset(); }
+        if let Some(dec) = self.audio_decoder.as_mut() { dec.decoder().reset(); }
+        self.seek_target = Some(position);
+        self.end_of_stream = false;
+        if self.state == PipelineState::Ended { self.state = PipelineState::Paused; }
         self.clock.seek(position);
+        self.video_renderer.clear();
+        self.time_update();
+        self.events.push(PipelineEvent::Seeked(position));
         Ok(())
     }
     
+    /// Video frame currently presented, for the compositing sink
+    pub fn current_video_frame(&self) -> Option<&VideoFrame> { self.video_renderer.current_frame() }
+    
+    pub fn video_renderer(&self) -> &VideoRenderer { &self.video_renderer }
+    
+    /// Keep hardware-decoded frames as GPU surfaces for a compositor that
+    /// imports them, instead of reading them back into planes
+    pub fn set_zero_copy(&mut self, enabled: bool) {
+        if let Some(dec) = self.video_decoder.as_mut() { dec.set_zero_copy(enabled); }
+    }
+    
+    /// Video is being decoded by a hardware decoder
+    pub fn is_hardware_decoding(&self) -> bool {
+        self.video_decoder.as_ref().is_some_and(DecoderBackend::is_hardware)
+    }
+    
     /// Process one step of the pipeline
     pub fn step(&mut self) -> Result<(), &'static str> {
+        self.announce_init_data();
         if self.state != PipelineState::Playing { return Ok(()); }
         
+        // A sample that was waiting for its key goes first
+        if let Some(packet) = self.pending_encrypted.take() {
+            self.process_packet(packet)?;
+        }
+        
         // Read and decode packets
-        while self.video_queue.len() < 5 && self.audio_queue.len() < 10 {
+        while !self.end_of_stream && self.pending_encrypted.is_none() && self.video_queue.len() < 5 && self.audio_queue.len() < 10 {
             match self.demuxer.read_packet() {
-                Ok(packet) => self.process_packet(packet)?,
+                Ok(packet) => {
+                    self.process_packet(packet)?;
+                    self.announce_init_data();
+                }
                 Err(crate::containers::DemuxerError::EndOfStream) => {
-                    self.state = PipelineState::Ended;
+                    self.end_of_stream = true;
+                    // Drain frames held back for reordering
+                    if let Some(dec) = self.video_decoder.as_mut() {
+                        let frames = dec.flush();
+                        self.queue_video(frames);
+                    }
                     break;
                 }
                 Err(_) => break,
@@ -105,49 +297,105 @@ impl MediaPipeline {
         }
         
         // Render frames at correct time
+        self.clock.sync();
         let pos = self.clock.position();
         
+        // Present the latest due frame, dropping any it overtook
         while let Some(frame) = self.video_queue.first() {
-            if frame.pts <= pos {
-                let frame = self.video_queue.remove(0);
+            if frame.pts > pos { break; }
+            let frame = self.video_queue.remove(0);
+            if self.video_queue.first().is_some_and(|next| next.pts <= pos) {
+                self.frames_dropped += 1;
+            } else {
                 self.video_renderer.render(&frame);
-            } else { break; }
+            }
         }
         
+        // Audio goes out ahead so the device has it buffered when due
+        let audio_horizon = pos + AUDIO_LEAD.mul_f64(self.clock.playback_rate());
         while let Some(samples) = self.audio_queue.first() {
-            if samples.pts <= pos {
+            if samples.pts <= audio_horizon {
                 let samples = self.audio_queue.remove(0);
                 self.audio_renderer.render(&samples);
             } else { break; }
         }
         
+        if self.end_of_stream && self.pending_encrypted.is_none() && self.video_queue.is_empty() && self.audio_queue.is_empty() {
+            self.time_update();
+            self.events.push(PipelineEvent::Ended);
+            self.state = PipelineState::Ended;
+            self.clock.pause();
+        } else if self.last_time_update.is_none_or(|at| at.elapsed() >= TIMEUPDATE_INTERVAL) {
+            self.time_update();
+        }
         Ok(())
     }
     
-    fn process_packet(&mut self, packet: Packet) -> Result<(), &'static str> {
+    /// Decrypt a protected sample in place; false if it has to wait for a key
+    /// (the sample is then held in `pending_encrypted`) or cannot be decrypted
+    fn decrypt(&mut self, packet: &mut Packet) -> bool {
+        let Some(encryption) = packet.encryption.take() else { return true };
+        let result = match &self.media_keys {
+            Some(keys) => keys.lock().map_err(|_| EmeError::InvalidState)
+                .and_then(|keys| keys.decrypt(&encryption, &mut packet.data)),
+            None => Err(EmeError::KeyNotFound),
+        };
+        match result {
+            Ok(()) => {
+                self.waiting_for_key = false;
+                true
+            }
+            Err(EmeError::KeyNotFound) => {
+                packet.encryption = Some(encryption);
+                if !self.waiting_for_key {
+                    self.events.push(PipelineEvent::WaitingForKey);
+                    self.waiting_for_key = true;
+                }
+                self.pending_encrypted = Some(packet.clone());
+                false
+            }
+            // Undecryptable samples are dropped like corrupt ones
+            Err(_) => false,
+        }
+    }
+    
+    fn process_packet(&mut self, mut packet: Packet) -> Result<(), &'static str> {
         let is_video = self.demuxer.video_track().map(|t| t.track_id == packet.track_id).unwrap_or(false);
         let is_audio = self.demuxer.audio_track().map(|t| t.track_id == packet.track_id).unwrap_or(false);
         
-        let encoded = packet.to_encoded_packet();
+        if !self.decrypt(&mut packet) { return Ok(()); }
+        let encoded = EncodedPacket::from(&packet);
         
         if is_video {
             if let Some(ref mut dec) = self.video_decoder {
                 if let Ok(frames) = dec.decode(&encoded) {
-                    self.video_queue.extend(frames);
+                    self.queue_video(frames);
                 }
             }
         } else if is_audio {
             if let Some(ref mut dec) = self.audio_decoder {
-                let samples = match dec {
-                    AudioDecoderBackend::Aac(d) => d.decode(&encoded),
-                    AudioDecoderBackend::Vorbis(d) => d.decode(&encoded),
-                };
-                if let Ok(s) = samples { self.audio_queue.push(s); }
+                // Header packets decode to no samples
+                if let Ok(s) = dec.decoder().decode(&encoded) {
+                    if !s.data.is_empty() { self.audio_queue.push(s); }
+                }
             }
         }
         
         Ok(())
     }
+    
+    /// Queue decoded frames in presentation order, skipping frames that end
+    /// before a pending seek target
+    fn queue_video(&mut self, frames: Vec<VideoFrame>) {
+        for frame in frames {
+            if let Some(target) = self.seek_target {
+                if frame.pts + frame.duration <= target { continue; }
+                self.seek_target = None;
+            }
+            let pos = self.video_queue.partition_point(|f| f.pts <= frame.pts);
+            self.video_queue.insert(pos, frame);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -155,4 +403,151 @@ mod tests {
     use super::*;
     #[test]
     fn test_state() { assert_eq!(PipelineState::Idle, PipelineState::Idle); }
+    
+    struct EmptyDemuxer;
+    
+    impl Demuxer for EmptyDemuxer {
+        fn duration(&self) -> Option<Duration> { None }
+        fn video_track(&self) -> Option<&crate::containers::TrackInfo> { None }
+        fn audio_track(&self) -> Option<&crate::containers::TrackInfo> { None }
+        fn read_packet(&mut self) -> crate::containers::DemuxerResult<Packet> { Err(crate::containers::DemuxerError::EndOfStream) }
+        fn seek(&mut self, _position: Duration) -> crate::containers::DemuxerResult<()> { Ok(()) }
+        fn is_eof(&self) -> bool { true }
+    }
+    
+    /// One encrypted packet behind a `pssh` box
+    struct EncryptedDemuxer {
+        packet: Option<Packet>,
+        init_data: Vec<InitData>,
+    }
+    
+    impl Demuxer for EncryptedDemuxer {
+        fn duration(&self) -> Option<Duration> { None }
+        fn video_track(&self) -> Option<&crate::containers::TrackInfo> { None }
+        fn audio_track(&self) -> Option<&crate::containers::TrackInfo> { None }
+        fn read_packet(&mut self) -> crate::containers::DemuxerResult<Packet> {
+            self.packet.take().ok_or(crate::containers::DemuxerError::EndOfStream)
+        }
+        fn seek(&mut self, _position: Duration) -> crate::containers::DemuxerResult<()> { Ok(()) }
+        fn is_eof(&self) -> bool { self.packet.is_none() }
+        fn init_data(&self) -> &[InitData] { &self.init_data }
+    }
+    
+    fn frame(pts_ms: u64) -> VideoFrame {
+        VideoFrame {
+            pts: Duration::from_millis(pts_ms), dts: Duration::ZERO, duration: Duration::from_millis(40),
+            width: 2, height: 2, format: crate::decoders::PixelFormat::I420, key_frame: false, planes: Vec::new(), surface: None,
+        }
+    }
+    
+    #[test]
+    fn test_seek_discards_early_frames() {
+        let mut p = MediaPipeline::new(Box::new(EmptyDemuxer));
+        p.seek(Duration::from_millis(100)).unwrap();
+        p.queue_video(vec![frame(0), frame(40), frame(120), frame(80)]);
+        let pts: Vec<u64> = p.video_queue.iter().map(|f| f.pts.as_millis() as u64).collect();
+        assert_eq!(pts, vec![80, 120]);
+    }
+    
+    #[test]
+    fn test_ends_after_queue_drains() {
+        let mut p = MediaPipeline::new(Box::new(EmptyDemuxer));
+        p.play();
+        p.step().unwrap();
+        assert_eq!(p.state(), PipelineState::Ended);
+        assert!(matches!(p.take_events()[..], [PipelineEvent::TimeUpdate(_), PipelineEvent::Ended]));
+    }
+    
+    #[test]
+    fn test_drops_overtaken_frames() {
+        let mut p = MediaPipeline::new(Box::new(EmptyDemuxer));
+        p.seek(Duration::from_millis(100)).unwrap();
+        p.queue_video(vec![frame(80), frame(120)]);
+        p.play();
+        p.step().unwrap();
+        assert_eq!(p.frames_dropped(), 0);
+        assert_eq!(p.current_video_frame().map(|f| f.pts), Some(Duration::from_millis(80)));
+        
+        p.clock.seek(Duration::from_millis(200));
+        p.queue_video(vec![frame(160), frame(10_000)]);
+        p.step().unwrap();
+        assert_eq!(p.frames_dropped(), 1);
+        assert_eq!(p.current_video_frame().map(|f| f.pts), Some(Duration::from_millis(160)));
+    }
+    
+    #[test]
+    fn test_waits_for_key() {
+        use crate::eme::{license, KeySystem, MediaKeySessionType, MediaKeys};
+        use crate::containers::SampleEncryption;
+        
+        let kid = [5u8; 16];
+        let init = InitData { init_data_type: "cenc".into(), data: vec![0; 32] };
+        let packet = Packet {
+            track_id: 1, pts: Duration::ZERO, dts: Duration::ZERO, duration: Duration::ZERO, is_key: true,
+            data: vec![0; 16],
+            encryption: Some(SampleEncryption { scheme: *b"cenc", key_id: kid, iv: vec![0; 8], subsamples: Vec::new() }),
+        };
+        let mut p = MediaPipeline::new(Box::new(EncryptedDemuxer { packet: Some(packet), init_data: vec![init.clone()] }));
+        let keys = std::sync::Arc::new(std::sync::Mutex::new(MediaKeys::new(KeySystem::ClearKey)));
+        p.set_media_keys(Some(keys.clone()));
+        p.play();
+        p.step().unwrap();
+        p.step().unwrap();
+        assert!(p.is_waiting_for_key());
+        assert_eq!(p.state(), PipelineState::Playing);
+        assert_eq!(p.take_events()[..2], [PipelineEvent::Encrypted(init), PipelineEvent::WaitingForKey]);
+        
+        {
+            let mut keys = keys.lock().unwrap();
+            let session = keys.create_session(MediaKeySessionType::Temporary);
+            session.generate_request("webm", &kid).unwrap();
+            let jwk = format!(r#"{{"keys":[{{"kty":"oct","kid":"{}","k":"{}"}}]}}"#, license::base64url_encode(&kid), license::base64url_encode(&[9; 16]));
+            session.update(jwk.as_bytes()).unwrap();
+        }
+        p.step().unwrap();
+        assert!(!p.is_waiting_for_key());
+        assert_eq!(p.state(), PipelineState::Ended);
+    }
+    
+    #[test]
+    fn test_rate_and_seek_events() {
+        let mut p = MediaPipeline::new(Box::new(EmptyDemuxer));
+        p.set_playback_rate(2.0);
+        p.set_playback_rate(2.0);
+        assert_eq!(p.audio_rate_mode(), renderer::AudioRateMode::TimeStretch);
+        p.set_preserves_pitch(false);
+        assert_eq!(p.audio_rate_mode(), renderer::AudioRateMode::Resample);
+        p.set_playback_rate(10.0);
+        assert_eq!(p.audio_rate_mode(), renderer::AudioRateMode::Muted);
+        p.seek(Duration::from_secs(3)).unwrap();
+        assert_eq!(p.take_events(), vec![
+            PipelineEvent::RateChange(2.0),
+            PipelineEvent::RateChange(10.0),
+            PipelineEvent::TimeUpdate(Duration::from_secs(3)),
+            PipelineEvent::Seeked(Duration::from_secs(3)),
+        ]);
+        assert_eq!(p.position(), Duration::from_secs(3));
+    }
+    
+    #[test]
+    fn test_element_sync() {
+        let mut p = MediaPipeline::new(Box::new(EmptyDemuxer));
+        let mut media = crate::HTMLMediaElement::new();
+        p.seek(Duration::from_millis(1500)).unwrap();
+        assert!(!media.sync_pipeline(&mut p));
+        assert_eq!(media.current_time, 1.5);
+        
+        media.playback_rate = 0.5;
+        media.preserves_pitch = false;
+        assert!(media.sync_pipeline(&mut p));
+        assert_eq!(p.audio_rate_mode(), renderer::AudioRateMode::Resample);
+        assert_eq!(media.current_time, 1.5);
+        assert_eq!(p.clock_source(), clock::ClockSource::System);
+        
+        // Out-of-range rates are clamped once, not re-applied every sync
+        media.playback_rate = 100.0;
+        assert!(media.sync_pipeline(&mut p));
+        assert!(!media.sync_pipeline(&mut p));
+        assert_eq!(media.playback_rate, 100.0);
+    }
 }
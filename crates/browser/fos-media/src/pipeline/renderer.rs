@@ -3,6 +3,11 @@
 //! Video and audio rendering output.
 
 use crate::decoders::{VideoFrame, AudioSamples, PixelFormat};
+use crate::decoders::hw::HwSurface;
+use crate::decoders::video::yuv::i420_to_rgba;
+use crate::output::SharedSampleQueue;
+use super::wsola::Wsola;
+use std::ops::RangeInclusive;
 use std::time::Duration;
 
 /// Video renderer
@@ -11,6 +16,8 @@ pub struct VideoRenderer {
     last_frame_time: Duration,
     frames_rendered: u64,
     target_texture: Option<TextureHandle>,
+    /// Frame currently on screen, kept for the compositor
+    current_frame: Option<VideoFrame>,
 }
 
 /// Texture handle for GPU rendering
@@ -18,7 +25,7 @@ pub struct VideoRenderer {
 pub struct TextureHandle { pub id: u32, pub width: u32, pub height: u32 }
 
 impl VideoRenderer {
-    pub fn new() -> Self { Self { last_frame_time: Duration::ZERO, frames_rendered: 0, target_texture: None } }
+    pub fn new() -> Self { Self { last_frame_time: Duration::ZERO, frames_rendered: 0, target_texture: None, current_frame: None } }
     
     pub fn render(&mut self, frame: &VideoFrame) {
         // In real impl: upload YUV data to GPU texture, run shader for YUV->RGB conversion
@@ -29,6 +36,46 @@ impl VideoRenderer {
         if self.target_texture.as_ref().map(|t| t.width != frame.width || t.height != frame.height).unwrap_or(true) {
             self.target_texture = Some(TextureHandle { id: 1, width: frame.width, height: frame.height });
         }
+        self.current_frame = Some(frame.clone());
+    }
+    
+    /// Forget the displayed frame (seek, source change)
+    pub fn clear(&mut self) { self.current_frame = None; }
+    
+    pub fn current_frame(&self) -> Option<&VideoFrame> { self.current_frame.as_ref() }
+    
+    /// Hardware surface of the displayed frame, for compositors that import
+    /// it without a copy
+    pub fn current_surface(&self) -> Option<&HwSurface> {
+        self.current_frame.as_ref()?.surface.as_ref()
+    }
+    
+    /// Displayed frame converted to RGBA for software compositing
+    pub fn frame_rgba(&self) -> Option<(u32, u32, Vec<u8>)> {
+        let frame = self.current_frame.as_ref()?;
+        let (w, h) = (frame.width as usize, frame.height as usize);
+        let mut rgba = vec![0u8; w * h * 4];
+        match (frame.format, frame.planes.as_slice()) {
+            (PixelFormat::I420, [y, u, v, ..]) => {
+                i420_to_rgba(&y.data, y.stride, &u.data, u.stride, &v.data, v.stride, w, h, &mut rgba);
+            }
+            (PixelFormat::Nv12, [y, uv, ..]) => {
+                // Split the interleaved chroma of hardware readbacks
+                let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+                let mut u = vec![128u8; cw * ch];
+                let mut v = vec![128u8; cw * ch];
+                for row in 0..ch {
+                    let line = uv.data.get(row * uv.stride..).unwrap_or(&[]);
+                    for (col, pair) in line.chunks_exact(2).take(cw).enumerate() {
+                        u[row * cw + col] = pair[0];
+                        v[row * cw + col] = pair[1];
+                    }
+                }
+                i420_to_rgba(&y.data, y.stride, &u, cw, &v, cw, w, h, &mut rgba);
+            }
+            _ => return None,
+        }
+        Some((frame.width, frame.height, rgba))
     }
     
     pub fn texture(&self) -> Option<&TextureHandle> { self.target_texture.as_ref() }
@@ -37,6 +84,36 @@ impl VideoRenderer {
 
 impl Default for VideoRenderer { fn default() -> Self { Self::new() } }
 
+/// Playback rates at which audio stays audible; outside it audio is muted
+pub const AUDIBLE_PLAYBACK_RATES: RangeInclusive<f64> = 0.25..=4.0;
+
+/// How audio follows the playback rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioRateMode {
+    /// Rate 1.0, samples pass through
+    Normal,
+    /// Tempo changed with WSOLA, pitch kept (`preservesPitch`)
+    TimeStretch,
+    /// Resampled, pitch follows the rate
+    Resample,
+    /// Rate outside [`AUDIBLE_PLAYBACK_RATES`]
+    Muted,
+}
+
+impl AudioRateMode {
+    pub fn for_rate(rate: f64, preserves_pitch: bool) -> Self {
+        if rate == 1.0 {
+            Self::Normal
+        } else if !AUDIBLE_PLAYBACK_RATES.contains(&rate) {
+            Self::Muted
+        } else if preserves_pitch {
+            Self::TimeStretch
+        } else {
+            Self::Resample
+        }
+    }
+}
+
 /// Audio renderer
 #[derive(Debug)]
 pub struct AudioRenderer {
@@ -44,22 +121,118 @@ pub struct AudioRenderer {
     channels: u32,
     buffer: Vec<f32>,
     samples_rendered: u64,
+    /// Mixer input on the audio output device
+    output: Option<SharedSampleQueue>,
+    rate: f64,
+    rate_mode: AudioRateMode,
+    stretcher: Option<Wsola>,
+    /// Media time of the next stretched block
+    stretch_pts: Option<Duration>,
 }
 
 impl AudioRenderer {
-    pub fn new() -> Self { Self { sample_rate: 48000, channels: 2, buffer: Vec::with_capacity(4096), samples_rendered: 0 } }
+    pub fn new() -> Self {
+        Self {
+            sample_rate: 48000, channels: 2, buffer: Vec::with_capacity(4096), samples_rendered: 0, output: None,
+            rate: 1.0, rate_mode: AudioRateMode::Normal, stretcher: None, stretch_pts: None,
+        }
+    }
+    
+    /// Route decoded audio to an output mixer input
+    pub fn connect_output(&mut self, queue: SharedSampleQueue) { self.output = Some(queue); }
+    pub fn disconnect_output(&mut self) { self.output = None; }
+    pub fn output(&self) -> Option<&SharedSampleQueue> { self.output.as_ref() }
+    
+    pub fn set_playback_rate(&mut self, rate: f64, preserves_pitch: bool) {
+        self.rate = rate;
+        self.rate_mode = AudioRateMode::for_rate(rate, preserves_pitch);
+        match (self.rate_mode, self.stretcher.as_mut()) {
+            (AudioRateMode::TimeStretch, Some(stretcher)) => stretcher.set_rate(rate),
+            (AudioRateMode::TimeStretch, None) => {}
+            _ => { self.stretcher = None; self.stretch_pts = None; }
+        }
+    }
+    
+    pub fn rate_mode(&self) -> AudioRateMode { self.rate_mode }
     
     pub fn render(&mut self, samples: &AudioSamples) {
-        // In real impl: push to audio output device via ALSA/PulseAudio/CoreAudio/WASAPI
         self.sample_rate = samples.sample_rate;
         self.channels = samples.channels;
-        self.samples_rendered += samples.data.len() as u64 / samples.channels as u64;
+        self.samples_rendered += samples.data.len() as u64 / samples.channels.max(1) as u64;
+        let block = match self.rate_mode {
+            AudioRateMode::Normal => None,
+            AudioRateMode::Muted => return,
+            AudioRateMode::TimeStretch => match self.stretch(samples) {
+                Some(block) => Some(block),
+                None => return,
+            },
+            AudioRateMode::Resample => Some(AudioSamples {
+                pts: samples.pts,
+                duration: samples.duration,
+                sample_rate: samples.sample_rate,
+                channels: samples.channels,
+                data: resample_by_rate(&samples.data, samples.channels, self.rate),
+            }),
+        };
+        if let Some(queue) = &self.output {
+            if let Ok(mut queue) = queue.lock() {
+                queue.push(block.as_ref().unwrap_or(samples));
+            }
+        }
+    }
+    
+    /// Run samples through the stretcher; the result spans the same media
+    /// time in `1 / rate` of the device time
+    fn stretch(&mut self, samples: &AudioSamples) -> Option<AudioSamples> {
+        let rate = self.rate;
+        let stretcher = self.stretcher.get_or_insert_with(|| Wsola::new(samples.channels, samples.sample_rate, rate));
+        if stretcher.channels() != samples.channels.max(1) {
+            *stretcher = Wsola::new(samples.channels, samples.sample_rate, rate);
+        }
+        let data = stretcher.process(&samples.data);
+        let pts = *self.stretch_pts.get_or_insert(samples.pts);
+        if data.is_empty() { return None; }
+        let frames = data.len() / samples.channels.max(1) as usize;
+        let duration = Duration::from_secs_f64(frames as f64 * rate / samples.sample_rate.max(1) as f64);
+        self.stretch_pts = Some(pts + duration);
+        Some(AudioSamples { pts, duration, sample_rate: samples.sample_rate, channels: samples.channels, data })
+    }
+    
+    /// Drop audio queued for output (seek)
+    pub fn flush(&mut self) {
+        if let Some(stretcher) = self.stretcher.as_mut() { stretcher.reset(); }
+        self.stretch_pts = None;
+        if let Some(queue) = &self.output {
+            if let Ok(mut queue) = queue.lock() {
+                queue.clear();
+            }
+        }
     }
     
     pub fn samples_rendered(&self) -> u64 { self.samples_rendered }
     pub fn latency(&self) -> Duration { Duration::from_millis(20) } // Target latency
 }
 
+/// Speed interleaved audio up or down by resampling, shifting its pitch
+pub(crate) fn resample_by_rate(data: &[f32], channels: u32, rate: f64) -> Vec<f32> {
+    let ch = channels.max(1) as usize;
+    let frames = data.len() / ch;
+    if frames == 0 { return Vec::new(); }
+    let out_frames = (frames as f64 / rate).round() as usize;
+    let mut out = Vec::with_capacity(out_frames * ch);
+    for i in 0..out_frames {
+        let pos = i as f64 * rate;
+        let idx = (pos as usize).min(frames - 1);
+        let next = (idx + 1).min(frames - 1);
+        let frac = (pos - idx as f64) as f32;
+        for c in 0..ch {
+            let (a, b) = (data[idx * ch + c], data[next * ch + c]);
+            out.push(a + (b - a) * frac);
+        }
+    }
+    out
+}
+
 impl Default for AudioRenderer { fn default() -> Self { Self::new() } }
 
 #[cfg(test)]
@@ -67,4 +240,62 @@ mod tests {
     use super::*;
     #[test]
     fn test_renderers() { let v = VideoRenderer::new(); let a = AudioRenderer::new(); assert_eq!(v.frames_rendered(), 0); assert_eq!(a.samples_rendered(), 0); }
+    
+    #[test]
+    fn test_frame_rgba() {
+        use crate::decoders::Plane;
+        let mut v = VideoRenderer::new();
+        let frame = VideoFrame {
+            pts: Duration::ZERO, dts: Duration::ZERO, duration: Duration::ZERO, width: 2, height: 2,
+            format: PixelFormat::I420, key_frame: true,
+            planes: vec![Plane { data: vec![235; 4], stride: 2 }, Plane { data: vec![128], stride: 1 }, Plane { data: vec![128], stride: 1 }],
+            surface: None,
+        };
+        v.render(&frame);
+        let (w, h, rgba) = v.frame_rgba().unwrap();
+        assert_eq!((w, h, rgba.len()), (2, 2, 16));
+        assert_eq!((rgba[0], rgba[3]), (235, 255));
+        
+        // Hardware readback: interleaved chroma with a strong red difference
+        let nv12 = VideoFrame {
+            format: PixelFormat::Nv12,
+            planes: vec![Plane { data: vec![100; 4], stride: 2 }, Plane { data: vec![128, 228], stride: 2 }],
+            ..frame
+        };
+        v.render(&nv12);
+        let (_, _, rgba) = v.frame_rgba().unwrap();
+        assert_eq!((rgba[0], rgba[2]), (237, 100));
+        assert!(v.current_surface().is_none());
+    }
+    
+    #[test]
+    fn test_audio_rate_modes() {
+        use crate::output::SampleQueueSource;
+        use std::sync::{Arc, Mutex};
+        
+        assert_eq!(AudioRateMode::for_rate(1.0, true), AudioRateMode::Normal);
+        assert_eq!(AudioRateMode::for_rate(2.0, true), AudioRateMode::TimeStretch);
+        assert_eq!(AudioRateMode::for_rate(2.0, false), AudioRateMode::Resample);
+        assert_eq!(AudioRateMode::for_rate(8.0, true), AudioRateMode::Muted);
+        
+        let queue = Arc::new(Mutex::new(SampleQueueSource::new()));
+        let mut a = AudioRenderer::new();
+        a.connect_output(queue.clone());
+        let block = AudioSamples { pts: Duration::ZERO, duration: Duration::from_secs(1), sample_rate: 8000, channels: 1, data: vec![0.5; 8000] };
+        
+        a.set_playback_rate(2.0, false);
+        a.render(&block);
+        assert_eq!(queue.lock().unwrap().buffered_frames(), 4000);
+        
+        a.flush();
+        a.set_playback_rate(2.0, true);
+        a.render(&block);
+        let stretched = queue.lock().unwrap().buffered_frames();
+        assert!(stretched > 3500 && stretched <= 4000, "{}", stretched);
+        
+        a.flush();
+        a.set_playback_rate(8.0, true);
+        a.render(&block);
+        assert_eq!(queue.lock().unwrap().buffered_frames(), 0);
+    }
 }
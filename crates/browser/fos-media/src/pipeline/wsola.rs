@@ -0,0 +1,184 @@
+//! WSOLA Time Stretching
+//!
+//! Waveform-similarity overlap-add changes tempo without changing pitch, for
+//! `playbackRate` with `preservesPitch`. Every output hop takes an input window
+//! near its ideal (rate-scaled) position, shifted within a small search range
+//! to where it best continues the previous window, and overlap-adds it with a
+//! Hann window.
+
+/// Output hop length
+const HOP_MS: usize = 10;
+/// Correlation is computed on every n-th frame
+const CORRELATION_STRIDE: usize = 2;
+
+/// Streaming WSOLA time stretcher for interleaved audio
+#[derive(Debug, Clone)]
+pub struct Wsola {
+    channels: usize,
+    rate: f64,
+    /// Output frames per window step; windows are two hops long
+    hop: usize,
+    /// Search radius around the ideal position, in frames
+    search: usize,
+    window: Vec<f32>,
+    /// Buffered input, starting at absolute frame `input_start`
+    input: Vec<f32>,
+    input_start: usize,
+    /// Absolute input frame where the next window ideally starts
+    ideal: f64,
+    /// Start of the previous window
+    prev: Option<usize>,
+    /// Windowed second half of the previous window
+    overlap: Vec<f32>,
+}
+
+impl Wsola {
+    pub fn new(channels: u32, sample_rate: u32, rate: f64) -> Self {
+        let channels = channels.max(1) as usize;
+        let hop = (sample_rate as usize * HOP_MS / 1000).max(16);
+        let window = (0..hop * 2)
+            .map(|i| (0.5 - 0.5 * (std::f64::consts::TAU * i as f64 / (hop * 2) as f64).cos()) as f32)
+            .collect();
+        Self {
+            channels,
+            rate: rate.max(f64::EPSILON),
+            hop,
+            search: hop / 2,
+            window,
+            input: Vec::new(),
+            input_start: 0,
+            ideal: 0.0,
+            prev: None,
+            overlap: vec![0.0; hop * channels],
+        }
+    }
+
+    pub fn rate(&self) -> f64 { self.rate }
+
+    /// Change the tempo; takes effect from the next window
+    pub fn set_rate(&mut self, rate: f64) {
+        self.rate = rate.max(f64::EPSILON);
+    }
+
+    pub fn channels(&self) -> u32 { self.channels as u32 }
+
+    /// Drop buffered audio (seek)
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.input_start = 0;
+        self.ideal = 0.0;
+        self.prev = None;
+        self.overlap.fill(0.0);
+    }
+
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        self.input[(frame - self.input_start) * self.channels + channel]
+    }
+
+    /// Downmixed sample for correlation
+    fn mono(&self, frame: usize) -> f32 {
+        (0..self.channels).map(|c| self.sample(frame, c)).sum()
+    }
+
+    /// Candidate start in `lo..=hi` whose first hop best matches the hop at `target`
+    fn best_match(&self, target: usize, lo: usize, hi: usize) -> usize {
+        let mut best = (f32::MIN, target.clamp(lo, hi));
+        for candidate in lo..=hi {
+            let (mut dot, mut energy) = (0.0f32, 0.0f32);
+            for i in (0..self.hop).step_by(CORRELATION_STRIDE) {
+                let c = self.mono(candidate + i);
+                dot += self.mono(target + i) * c;
+                energy += c * c;
+            }
+            let score = if energy > 0.0 { dot / energy.sqrt() } else { 0.0 };
+            if score > best.0 {
+                best = (score, candidate);
+            }
+        }
+        best.1
+    }
+
+    /// Feed interleaved input and return the stretched output produced so far
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        self.input.extend_from_slice(input);
+        let (ch, hop) = (self.channels, self.hop);
+        let mut out = Vec::new();
+        loop {
+            let ideal = (self.ideal.round() as usize).max(self.input_start);
+            let lo = ideal.saturating_sub(self.search).max(self.input_start);
+            let hi = ideal + self.search;
+            let available = self.input_start + self.input.len() / ch;
+            if hi + hop * 2 > available {
+                break;
+            }
+            let start = match self.prev {
+                Some(prev) => self.best_match(prev + hop, lo, hi),
+                None => ideal,
+            };
+
+            for i in 0..hop {
+                for c in 0..ch {
+                    out.push(self.overlap[i * ch + c] + self.sample(start + i, c) * self.window[i]);
+                }
+            }
+            for i in 0..hop {
+                for c in 0..ch {
+                    self.overlap[i * ch + c] = self.sample(start + hop + i, c) * self.window[hop + i];
+                }
+            }
+            self.prev = Some(start);
+            self.ideal += hop as f64 * self.rate;
+
+            // Keep what the next window and its match target can still use
+            let keep_from = (self.ideal as usize).saturating_sub(self.search).min(start + hop);
+            if keep_from > self.input_start {
+                self.input.drain(..(keep_from - self.input_start) * ch);
+                self.input_start = keep_from;
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frames: usize, freq: f64, rate: u32) -> Vec<f32> {
+        (0..frames).map(|i| (std::f64::consts::TAU * freq * i as f64 / rate as f64).sin() as f32).collect()
+    }
+
+    /// Average period from upward zero crossings
+    fn period(samples: &[f32]) -> f64 {
+        let crossings: Vec<usize> = samples.windows(2).enumerate()
+            .filter(|(_, w)| w[0] < 0.0 && w[1] >= 0.0)
+            .map(|(i, _)| i)
+            .collect();
+        (crossings[crossings.len() - 1] - crossings[0]) as f64 / (crossings.len() - 1) as f64
+    }
+
+    #[test]
+    fn test_tempo_changes_pitch_does_not() {
+        let input = sine(48000, 440.0, 48000);
+        for rate in [0.5, 2.0] {
+            let mut wsola = Wsola::new(1, 48000, rate);
+            let out: Vec<f32> = input.chunks(1000).flat_map(|c| wsola.process(c)).collect();
+            let expected = input.len() as f64 / rate;
+            assert!((out.len() as f64 - expected).abs() < expected * 0.05, "rate {}: {} frames", rate, out.len());
+            // Skip the fade-in of the first window
+            let period = period(&out[1000..]);
+            assert!((period - 48000.0 / 440.0).abs() < 1.0, "rate {}: period {}", rate, period);
+        }
+    }
+
+    #[test]
+    fn test_stereo_and_reset() {
+        let mut wsola = Wsola::new(2, 8000, 1.5);
+        let input: Vec<f32> = sine(4000, 200.0, 8000).into_iter().flat_map(|s| [s, -s]).collect();
+        let out = wsola.process(&input);
+        assert_eq!(out.len() % 2, 0);
+        assert!(out.chunks(2).all(|f| (f[0] + f[1]).abs() < 1e-5));
+        wsola.reset();
+        assert!(wsola.process(&input[..20]).is_empty());
+    }
+}
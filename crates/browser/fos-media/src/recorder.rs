@@ -0,0 +1,547 @@
+//! MediaRecorder
+//!
+//! Records a [`MediaStream`] to WebM. Video frames are encoded with VP8 and
+//! audio with Opus, using encoders from the [`CodecRegistry`], and muxed as
+//! they arrive. Recorded data is handed out in `dataavailable` chunks every
+//! `timeslice` and on `requestData()` and `stop()`; the first chunk starts
+//! with the WebM header, so the chunks concatenated form a playable file.
+//!
+//! Frames and samples are pushed by whoever produces the tracks (camera,
+//! screen or canvas capture, the audio graph); times passed in are the
+//! browser's monotonic clock.
+
+use std::time::Duration;
+
+use crate::capture::CapturedFrame;
+use crate::codecs::{AudioEncoder, CodecConfig, CodecError, CodecRegistry, CodecSupport, CodecType, VideoEncoder};
+use crate::containers::webm::WebMMuxer;
+use crate::containers::{CodecId, TrackInfo, TrackType};
+use crate::decoders::AudioSamples;
+use crate::webrtc::{MediaStream, MediaStreamTrackKind, MediaStreamTrackState};
+
+const DEFAULT_VIDEO_BITRATE: u32 = 2_500_000;
+const DEFAULT_AUDIO_BITRATE: u32 = 128_000;
+
+/// `MediaRecorder.state`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecordingState {
+    #[default]
+    Inactive,
+    Recording,
+    Paused,
+}
+
+impl RecordingState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Inactive => "inactive",
+            Self::Recording => "recording",
+            Self::Paused => "paused",
+        }
+    }
+}
+
+/// `MediaRecorderOptions`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaRecorderOptions {
+    /// Empty picks WebM with the codecs the stream needs
+    pub mime_type: String,
+    pub video_bits_per_second: Option<u32>,
+    pub audio_bits_per_second: Option<u32>,
+}
+
+/// MediaRecorder error
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RecorderError {
+    #[error("Not supported: {0}")]
+    NotSupported(String),
+
+    #[error("Invalid state")]
+    InvalidState,
+
+    #[error("Encoding failed: {0}")]
+    Encoding(String),
+}
+
+impl From<CodecError> for RecorderError {
+    fn from(e: CodecError) -> Self {
+        Self::Encoding(e.to_string())
+    }
+}
+
+/// Event fired at the recorder
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecorderEvent {
+    Start,
+    /// `dataavailable` with the Blob's bytes
+    DataAvailable(Vec<u8>),
+    Pause,
+    Resume,
+    Stop,
+    Error(RecorderError),
+}
+
+impl RecorderEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::DataAvailable(_) => "dataavailable",
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+            Self::Stop => "stop",
+            Self::Error(_) => "error",
+        }
+    }
+}
+
+/// A recorded track and its WebM track number
+#[derive(Debug)]
+struct RecordedTrack {
+    id: String,
+    number: u32,
+    kind: MediaStreamTrackKind,
+    ended: bool,
+    video: Option<VideoEncoder>,
+    audio: Option<AudioEncoder>,
+    /// First frame seen, for the WebM track header
+    info: Option<TrackInfo>,
+}
+
+/// MediaRecorder
+#[derive(Debug)]
+pub struct MediaRecorder {
+    mime_type: String,
+    options: MediaRecorderOptions,
+    registry: CodecRegistry,
+    state: RecordingState,
+    tracks: Vec<RecordedTrack>,
+    /// Created once every track has described itself
+    muxer: Option<WebMMuxer>,
+    /// Blocks encoded before the header could be written
+    pending_blocks: Vec<(u32, u64, bool, Vec<u8>)>,
+    /// Muxed bytes not yet handed out
+    data: Vec<u8>,
+    timeslice: Option<Duration>,
+    last_data: Duration,
+    started: Duration,
+    paused_at: Option<Duration>,
+    paused_total: Duration,
+    events: Vec<RecorderEvent>,
+}
+
+/// Codecs of a MIME type with their registry codec; `None` if it is not a
+/// WebM type or names an unknown codec
+fn parse_mime_type(mime_type: &str) -> Option<(bool, Vec<CodecType>)> {
+    let mut parts = mime_type.split(';');
+    let video = match parts.next()?.trim().to_ascii_lowercase().as_str() {
+        "video/webm" => true,
+        "audio/webm" => false,
+        _ => return None,
+    };
+    let mut codecs = Vec::new();
+    for param in parts {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("codecs") {
+            continue;
+        }
+        for codec in value.trim().trim_matches('"').split(',') {
+            codecs.push(match codec.trim().to_ascii_lowercase().as_str() {
+                "vp8" | "vp8.0" => CodecType::VP8,
+                "vp9" | "vp9.0" => CodecType::VP9,
+                "av1" => CodecType::AV1,
+                "opus" => CodecType::Opus,
+                "vorbis" => CodecType::Vorbis,
+                _ => return None,
+            });
+        }
+    }
+    if !video && codecs.iter().any(|c| c.is_video()) {
+        return None;
+    }
+    Some((video, codecs))
+}
+
+impl MediaRecorder {
+    /// `new MediaRecorder(stream, options)`
+    pub fn new(stream: &MediaStream, options: MediaRecorderOptions, registry: &CodecRegistry) -> Result<Self, RecorderError> {
+        if !options.mime_type.is_empty() && !Self::is_type_supported(registry, &options.mime_type) {
+            return Err(RecorderError::NotSupported(options.mime_type.clone()));
+        }
+        let has_video = !stream.get_video_tracks().is_empty();
+        let has_audio = !stream.get_audio_tracks().is_empty();
+        let mime_type = match (options.mime_type.is_empty(), has_video, has_audio) {
+            (false, _, _) => options.mime_type.clone(),
+            (true, true, true) => "video/webm;codecs=vp8,opus".to_string(),
+            (true, true, false) => "video/webm;codecs=vp8".to_string(),
+            (true, false, _) => "audio/webm;codecs=opus".to_string(),
+        };
+        Ok(Self {
+            mime_type,
+            options,
+            registry: registry.clone(),
+            state: RecordingState::Inactive,
+            tracks: stream.tracks.iter().enumerate().map(|(i, t)| RecordedTrack {
+                id: t.id.clone(),
+                number: i as u32 + 1,
+                kind: t.kind,
+                ended: t.ready_state == MediaStreamTrackState::Ended,
+                video: None,
+                audio: None,
+                info: None,
+            }).collect(),
+            muxer: None,
+            pending_blocks: Vec::new(),
+            data: Vec::new(),
+            timeslice: None,
+            last_data: Duration::ZERO,
+            started: Duration::ZERO,
+            paused_at: None,
+            paused_total: Duration::ZERO,
+            events: Vec::new(),
+        })
+    }
+
+    /// `MediaRecorder.isTypeSupported()`: WebM with codecs that have a
+    /// registered encoder
+    pub fn is_type_supported(registry: &CodecRegistry, mime_type: &str) -> bool {
+        parse_mime_type(mime_type).is_some_and(|(_, codecs)| {
+            codecs.iter().all(|&c| registry.is_encode_supported(c) == CodecSupport::Supported)
+        })
+    }
+
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    pub fn state(&self) -> RecordingState {
+        self.state
+    }
+
+    pub fn video_bits_per_second(&self) -> u32 {
+        self.options.video_bits_per_second.unwrap_or(DEFAULT_VIDEO_BITRATE)
+    }
+
+    pub fn audio_bits_per_second(&self) -> u32 {
+        self.options.audio_bits_per_second.unwrap_or(DEFAULT_AUDIO_BITRATE)
+    }
+
+    /// `start(timeslice)`
+    pub fn start(&mut self, timeslice: Option<Duration>, now: Duration) -> Result<(), RecorderError> {
+        if self.state != RecordingState::Inactive {
+            return Err(RecorderError::InvalidState);
+        }
+        if self.tracks.iter().all(|t| t.ended) {
+            return Err(RecorderError::NotSupported("stream is inactive".into()));
+        }
+        for track in &mut self.tracks {
+            track.video = None;
+            track.audio = None;
+            track.info = None;
+            if track.kind == MediaStreamTrackKind::Video {
+                let mut config = CodecConfig::video(CodecType::VP8, 0, 0);
+                config.bitrate = Some(self.options.video_bits_per_second.unwrap_or(DEFAULT_VIDEO_BITRATE));
+                track.video = Some(self.registry.create_video_encoder(config)?);
+            }
+        }
+        self.muxer = None;
+        self.pending_blocks.clear();
+        self.data.clear();
+        self.state = RecordingState::Recording;
+        self.timeslice = timeslice;
+        self.started = now;
+        self.last_data = now;
+        self.paused_at = None;
+        self.paused_total = Duration::ZERO;
+        self.events.push(RecorderEvent::Start);
+        Ok(())
+    }
+
+    /// `stop()`: hand out the remaining data and finish
+    pub fn stop(&mut self) {
+        if self.state == RecordingState::Inactive {
+            return;
+        }
+        let mut flushed = Vec::new();
+        for track in &mut self.tracks {
+            if let Some(audio) = track.audio.as_mut() {
+                flushed.extend(audio.flush().into_iter().map(|c| (track.number, c)));
+            }
+        }
+        for (number, chunk) in flushed {
+            self.write((number, (chunk.timestamp * 1000.0).round() as u64, chunk.is_key, chunk.data));
+        }
+        // Tracks that never got a frame are left out of the header
+        if self.muxer.is_none() && !self.pending_blocks.is_empty() {
+            self.write_header();
+        }
+        self.state = RecordingState::Inactive;
+        self.events.push(RecorderEvent::DataAvailable(std::mem::take(&mut self.data)));
+        self.events.push(RecorderEvent::Stop);
+    }
+
+    /// `pause()`
+    pub fn pause(&mut self, now: Duration) -> Result<(), RecorderError> {
+        match self.state {
+            RecordingState::Inactive => return Err(RecorderError::InvalidState),
+            RecordingState::Paused => return Ok(()),
+            RecordingState::Recording => {}
+        }
+        self.state = RecordingState::Paused;
+        self.paused_at = Some(now);
+        self.events.push(RecorderEvent::Pause);
+        Ok(())
+    }
+
+    /// `resume()`; the paused time is cut out of the recording
+    pub fn resume(&mut self, now: Duration) -> Result<(), RecorderError> {
+        match self.state {
+            RecordingState::Inactive => return Err(RecorderError::InvalidState),
+            RecordingState::Recording => return Ok(()),
+            RecordingState::Paused => {}
+        }
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_total += now.saturating_sub(paused_at);
+        }
+        self.state = RecordingState::Recording;
+        self.events.push(RecorderEvent::Resume);
+        Ok(())
+    }
+
+    /// `requestData()`
+    pub fn request_data(&mut self, now: Duration) -> Result<(), RecorderError> {
+        if self.state == RecordingState::Inactive {
+            return Err(RecorderError::InvalidState);
+        }
+        self.emit_data(now);
+        Ok(())
+    }
+
+    /// Fire `dataavailable` when the timeslice has elapsed
+    pub fn tick(&mut self, now: Duration) {
+        if self.state == RecordingState::Inactive {
+            return;
+        }
+        if self.timeslice.is_some_and(|slice| now.saturating_sub(self.last_data) >= slice) {
+            self.emit_data(now);
+        }
+    }
+
+    /// A video frame of a recorded track
+    pub fn push_video_frame(&mut self, track_id: &str, frame: &CapturedFrame, now: Duration) {
+        if self.state != RecordingState::Recording {
+            return;
+        }
+        let timestamp = self.media_time(now);
+        let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id && !t.ended) else { return };
+        let Some(encoder) = track.video.as_mut() else { return };
+        let chunk = match encoder.encode(frame, timestamp.as_secs_f64(), track.info.is_none()) {
+            Ok(chunk) => chunk,
+            Err(e) => return self.fail(e.into()),
+        };
+        track.info.get_or_insert_with(|| TrackInfo {
+            track_id: track.number,
+            track_type: TrackType::Video,
+            codec: CodecId::Vp8,
+            duration: Duration::ZERO,
+            timescale: 1000,
+            width: Some(frame.width),
+            height: Some(frame.height),
+            frame_rate: None,
+            sample_rate: None,
+            channels: None,
+            codec_private: Vec::new(),
+            encryption: None,
+        });
+        let block = (track.number, timestamp.as_millis() as u64, chunk.is_key, chunk.data);
+        self.write(block);
+    }
+
+    /// Audio of a recorded track; the encoder takes the format of the
+    /// first samples
+    pub fn push_audio(&mut self, track_id: &str, samples: &AudioSamples, now: Duration) {
+        if self.state != RecordingState::Recording {
+            return;
+        }
+        let timestamp = self.media_time(now);
+        let bitrate = self.audio_bits_per_second();
+        let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id && !t.ended) else { return };
+        if track.kind != MediaStreamTrackKind::Audio {
+            return;
+        }
+        if track.audio.is_none() {
+            let mut config = CodecConfig::audio(CodecType::Opus, samples.sample_rate, samples.channels);
+            config.bitrate = Some(bitrate);
+            match self.registry.create_audio_encoder(config) {
+                Ok(encoder) => {
+                    track.info = Some(TrackInfo {
+                        track_id: track.number,
+                        track_type: TrackType::Audio,
+                        codec: CodecId::Opus,
+                        duration: Duration::ZERO,
+                        timescale: 1000,
+                        width: None,
+                        height: None,
+                        frame_rate: None,
+                        sample_rate: Some(samples.sample_rate),
+                        channels: Some(samples.channels),
+                        codec_private: encoder.codec_private(),
+                        encryption: None,
+                    });
+                    track.audio = Some(encoder);
+                }
+                Err(e) => return self.fail(e.into()),
+            }
+        }
+        let number = track.number;
+        let chunks = match track.audio.as_mut().map(|a| a.encode(&samples.data, timestamp.as_secs_f64())) {
+            Some(Ok(chunks)) => chunks,
+            Some(Err(e)) => return self.fail(e.into()),
+            None => return,
+        };
+        for chunk in chunks {
+            self.write((number, (chunk.timestamp * 1000.0).round() as u64, chunk.is_key, chunk.data));
+        }
+    }
+
+    /// A recorded track ended; recording stops when all of them have
+    pub fn track_ended(&mut self, track_id: &str) {
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            track.ended = true;
+        }
+        if self.tracks.iter().all(|t| t.ended) {
+            self.stop();
+        }
+    }
+
+    /// Take events to fire, in order
+    pub fn take_events(&mut self) -> Vec<RecorderEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Recording time: time since start without the paused time
+    fn media_time(&self, now: Duration) -> Duration {
+        now.saturating_sub(self.started).saturating_sub(self.paused_total)
+    }
+
+    fn write(&mut self, block: (u32, u64, bool, Vec<u8>)) {
+        if self.muxer.is_none() {
+            self.pending_blocks.push(block);
+            // The header needs every live track's format
+            if self.tracks.iter().all(|t| t.ended || t.info.is_some()) {
+                self.write_header();
+            }
+            return;
+        }
+        if let Some(muxer) = self.muxer.as_mut() {
+            let (track, timestamp, key, data) = block;
+            self.data.extend(muxer.write_block(track, timestamp, key, &data));
+        }
+    }
+
+    fn write_header(&mut self) {
+        let tracks = self.tracks.iter().filter_map(|t| t.info.clone()).collect();
+        let mut muxer = match WebMMuxer::new(tracks) {
+            Ok(muxer) => muxer,
+            Err(e) => return self.fail(RecorderError::Encoding(e.to_string())),
+        };
+        self.data.extend(muxer.header());
+        let mut blocks = std::mem::take(&mut self.pending_blocks);
+        blocks.sort_by_key(|b| b.1);
+        for (track, timestamp, key, data) in blocks {
+            self.data.extend(muxer.write_block(track, timestamp, key, &data));
+        }
+        self.muxer = Some(muxer);
+    }
+
+    fn emit_data(&mut self, now: Duration) {
+        self.last_data = now;
+        self.events.push(RecorderEvent::DataAvailable(std::mem::take(&mut self.data)));
+    }
+
+    /// Encoding errors fire `error` and stop the recording
+    fn fail(&mut self, error: RecorderError) {
+        self.events.push(RecorderEvent::Error(error));
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::containers::webm::WebMDemuxer;
+    use crate::containers::Demuxer;
+    use crate::webrtc::MediaStreamTrack;
+
+    fn stream() -> MediaStream {
+        let mut stream = MediaStream::new();
+        for (id, kind) in [("v", MediaStreamTrackKind::Video), ("a", MediaStreamTrackKind::Audio)] {
+            stream.add_track(MediaStreamTrack {
+                id: id.into(),
+                kind,
+                label: String::new(),
+                enabled: true,
+                muted: false,
+                ready_state: MediaStreamTrackState::Live,
+            });
+        }
+        stream
+    }
+
+    #[test]
+    fn test_record_webm() {
+        let ms = Duration::from_millis;
+        let registry = CodecRegistry::new();
+        let mut recorder = MediaRecorder::new(&stream(), MediaRecorderOptions::default(), &registry).unwrap();
+        assert_eq!(recorder.mime_type(), "video/webm;codecs=vp8,opus");
+        recorder.start(Some(ms(100)), ms(1000)).unwrap();
+        assert_eq!(recorder.start(None, ms(1000)), Err(RecorderError::InvalidState));
+
+        let frame = CapturedFrame { width: 4, height: 2, data: vec![0; 32], timestamp: Duration::ZERO };
+        let audio = AudioSamples { pts: Duration::ZERO, duration: ms(20), sample_rate: 48000, channels: 1, data: vec![0.0; 960] };
+        recorder.push_audio("a", &audio, ms(1000));
+        recorder.push_video_frame("v", &frame, ms(1010));
+        recorder.pause(ms(1020)).unwrap();
+        recorder.push_video_frame("v", &frame, ms(1030));
+        recorder.resume(ms(1520)).unwrap();
+        recorder.push_video_frame("v", &frame, ms(1530));
+        recorder.tick(ms(1600));
+        recorder.stop();
+
+        let events = recorder.take_events();
+        let names: Vec<&str> = events.iter().map(|e| e.name()).collect();
+        assert_eq!(names, ["start", "pause", "resume", "dataavailable", "dataavailable", "stop"]);
+        let file: Vec<u8> = events.into_iter().flat_map(|e| match e {
+            RecorderEvent::DataAvailable(data) => data,
+            _ => Vec::new(),
+        }).collect();
+
+        let mut demuxer = WebMDemuxer::new(file).unwrap();
+        assert_eq!(demuxer.video_track().unwrap().width, Some(4));
+        assert_eq!(&demuxer.audio_track().unwrap().codec_private[..8], b"OpusHead");
+        let packets: Vec<(u32, u128)> = std::iter::from_fn(|| demuxer.read_packet().ok())
+            .map(|p| (p.track_id, p.pts.as_millis()))
+            .collect();
+        // The paused half second is cut out
+        assert_eq!(packets, [(2, 0), (1, 10), (1, 30)]);
+    }
+
+    #[test]
+    fn test_type_support() {
+        let registry = CodecRegistry::new();
+        assert!(MediaRecorder::is_type_supported(&registry, "video/webm;codecs=\"vp8, opus\""));
+        assert!(MediaRecorder::is_type_supported(&registry, "audio/webm"));
+        assert!(!MediaRecorder::is_type_supported(&registry, "video/webm;codecs=vp9"));
+        assert!(!MediaRecorder::is_type_supported(&registry, "audio/webm;codecs=vp8"));
+        assert!(!MediaRecorder::is_type_supported(&registry, "video/mp4"));
+
+        let options = MediaRecorderOptions { mime_type: "video/mp4".into(), ..Default::default() };
+        assert!(matches!(MediaRecorder::new(&stream(), options, &registry), Err(RecorderError::NotSupported(_))));
+        let mut recorder = MediaRecorder::new(&stream(), MediaRecorderOptions::default(), &registry).unwrap();
+        assert_eq!(recorder.request_data(Duration::ZERO), Err(RecorderError::InvalidState));
+        recorder.start(None, Duration::ZERO).unwrap();
+        recorder.track_ended("v");
+        assert_eq!(recorder.state(), RecordingState::Recording);
+        recorder.track_ended("a");
+        assert_eq!(recorder.state(), RecordingState::Inactive);
+    }
+}
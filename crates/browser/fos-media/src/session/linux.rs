@@ -0,0 +1,194 @@
+//! MPRIS Media Keys (Linux)
+//!
+//! Exports org.mpris.MediaPlayer2 and org.mpris.MediaPlayer2.Player on the
+//! session bus, which desktop shells, media key daemons and playerctl use.
+//!
+//! This is a stub implementation for the bus connection. Property and method
+//! mapping is complete; full implementation would register the object at
+//! /org/mpris/MediaPlayer2 with libdbus/zbus, emit PropertiesChanged on each
+//! update and feed incoming method calls into [`MprisMediaKeys::method_call`].
+
+use super::{MediaKeyBackend, MediaSessionAction, MediaSessionActionDetails, NowPlaying};
+use std::collections::VecDeque;
+
+/// D-Bus value of an MPRIS property
+#[derive(Debug, Clone, PartialEq)]
+pub enum MprisValue {
+    Str(String),
+    StrList(Vec<String>),
+    /// Microseconds (x) or object path stand-ins
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+}
+
+fn micros(seconds: f64) -> i64 {
+    (seconds * 1_000_000.0) as i64
+}
+
+/// `Metadata` dictionary (xesam / mpris keys)
+pub(crate) fn mpris_metadata(now_playing: &NowPlaying) -> Vec<(&'static str, MprisValue)> {
+    let metadata = &now_playing.metadata;
+    let mut map = vec![("mpris:trackid", MprisValue::Str("/org/fos/MediaSession/Track".into()))];
+    if !metadata.title.is_empty() {
+        map.push(("xesam:title", MprisValue::Str(metadata.title.clone())));
+    }
+    if !metadata.artist.is_empty() {
+        map.push(("xesam:artist", MprisValue::StrList(vec![metadata.artist.clone()])));
+    }
+    if !metadata.album.is_empty() {
+        map.push(("xesam:album", MprisValue::Str(metadata.album.clone())));
+    }
+    // Shells show a single image; the last artwork entry is usually the largest
+    if let Some(art) = metadata.artwork.last() {
+        map.push(("mpris:artUrl", MprisValue::Str(art.src.clone())));
+    }
+    if let Some(position) = now_playing.position.filter(|p| p.duration.is_finite()) {
+        map.push(("mpris:length", MprisValue::I64(micros(position.duration))));
+    }
+    map
+}
+
+/// org.mpris.MediaPlayer2.Player properties other than `Metadata`
+pub(crate) fn mpris_player_properties(now_playing: &NowPlaying) -> Vec<(&'static str, MprisValue)> {
+    let can = |action| MprisValue::Bool(now_playing.actions.contains(&action));
+    let status = if now_playing.playing { "Playing" } else { "Paused" };
+    vec![
+        ("PlaybackStatus", MprisValue::Str(status.into())),
+        ("Rate", MprisValue::F64(now_playing.position.map_or(1.0, |p| p.playback_rate))),
+        ("Position", MprisValue::I64(now_playing.position.map_or(0, |p| micros(p.position)))),
+        ("CanPlay", can(MediaSessionAction::Play)),
+        ("CanPause", can(MediaSessionAction::Pause)),
+        ("CanSeek", can(MediaSessionAction::SeekTo)),
+        ("CanGoNext", can(MediaSessionAction::NextTrack)),
+        ("CanGoPrevious", can(MediaSessionAction::PreviousTrack)),
+        ("CanControl", MprisValue::Bool(true)),
+    ]
+}
+
+/// Map a Player method call to an action; `arg` is the microsecond
+/// argument of Seek / SetPosition
+pub(crate) fn parse_mpris_call(method: &str, arg: Option<i64>, playing: bool) -> Option<MediaSessionActionDetails> {
+    let seconds = || arg.map(|us| us as f64 / 1_000_000.0);
+    Some(match method {
+        "Play" => MediaSessionActionDetails::new(MediaSessionAction::Play),
+        "Pause" => MediaSessionActionDetails::new(MediaSessionAction::Pause),
+        "PlayPause" => MediaSessionActionDetails::toggle(playing),
+        "Stop" => MediaSessionActionDetails::new(MediaSessionAction::Stop),
+        "Next" => MediaSessionActionDetails::new(MediaSessionAction::NextTrack),
+        "Previous" => MediaSessionActionDetails::new(MediaSessionAction::PreviousTrack),
+        "Seek" => MediaSessionActionDetails::seek_by(seconds()?),
+        "SetPosition" => MediaSessionActionDetails::seek_to(seconds()?.max(0.0)),
+        _ => return None,
+    })
+}
+
+/// MPRIS player on the session bus
+#[derive(Debug)]
+pub struct MprisMediaKeys {
+    /// Well-known bus name, unique per browser process
+    bus_name: String,
+    // In real impl: bus connection and exported object
+    published: Option<NowPlaying>,
+    /// Exported Metadata entries followed by the other Player properties
+    properties: Vec<(&'static str, MprisValue)>,
+    pending: VecDeque<MediaSessionActionDetails>,
+}
+
+impl MprisMediaKeys {
+    pub fn new() -> Self {
+        Self {
+            bus_name: format!("org.mpris.MediaPlayer2.fos.instance{}", std::process::id()),
+            published: None,
+            properties: Vec::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// MPRIS needs a D-Bus session bus
+    pub fn is_available() -> bool {
+        std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some()
+    }
+
+    pub fn bus_name(&self) -> &str { &self.bus_name }
+
+    /// Currently exported property (Metadata keys included)
+    pub fn property(&self, name: &str) -> Option<&MprisValue> {
+        self.properties.iter().find(|(key, _)| *key == name).map(|(_, value)| value)
+    }
+
+    /// Incoming org.mpris.MediaPlayer2.Player method call
+    pub fn method_call(&mut self, method: &str, arg: Option<i64>) {
+        let playing = self.published.as_ref().is_some_and(|n| n.playing);
+        if let Some(details) = parse_mpris_call(method, arg, playing) {
+            self.pending.push_back(details);
+        }
+    }
+}
+
+impl Default for MprisMediaKeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MediaKeyBackend for MprisMediaKeys {
+    fn name(&self) -> &'static str { "mpris" }
+
+    fn update(&mut self, now_playing: &NowPlaying) {
+        // Real impl would:
+        // 1. RequestName(bus_name) on first update
+        // 2. Set Metadata = mpris_metadata(), plus mpris_player_properties()
+        // 3. Emit org.freedesktop.DBus.Properties.PropertiesChanged, and
+        //    Seeked(Position) when the position jumped
+        self.properties = mpris_metadata(now_playing);
+        self.properties.extend(mpris_player_properties(now_playing));
+        self.published = Some(now_playing.clone());
+    }
+
+    fn clear(&mut self) {
+        // Real impl: ReleaseName(bus_name) so shells drop the player
+        self.published = None;
+        self.properties.clear();
+        self.pending.clear();
+    }
+
+    fn poll_action(&mut self) -> Option<MediaSessionActionDetails> {
+        self.pending.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{MediaMetadata, MediaPositionState};
+
+    #[test]
+    fn test_mpris_mapping() {
+        let now_playing = NowPlaying {
+            metadata: MediaMetadata { title: "Song".into(), artist: "Band".into(), ..Default::default() },
+            playing: true,
+            position: Some(MediaPositionState { duration: 200.0, playback_rate: 1.0, position: 12.5 }),
+            actions: vec![MediaSessionAction::Play, MediaSessionAction::Pause, MediaSessionAction::SeekTo],
+        };
+        let metadata = mpris_metadata(&now_playing);
+        assert!(metadata.contains(&("xesam:artist", MprisValue::StrList(vec!["Band".into()]))));
+        assert!(metadata.contains(&("mpris:length", MprisValue::I64(200_000_000))));
+        let properties = mpris_player_properties(&now_playing);
+        assert!(properties.contains(&("Position", MprisValue::I64(12_500_000))));
+        assert!(properties.contains(&("CanGoNext", MprisValue::Bool(false))));
+
+        assert_eq!(parse_mpris_call("PlayPause", None, true).unwrap().action, MediaSessionAction::Pause);
+        let seek = parse_mpris_call("Seek", Some(-5_000_000), true).unwrap();
+        assert_eq!((seek.action, seek.seek_offset), (MediaSessionAction::SeekBackward, Some(5.0)));
+        assert_eq!(parse_mpris_call("SetPosition", Some(30_000_000), true).unwrap().seek_time, Some(30.0));
+        assert!(parse_mpris_call("Seek", None, true).is_none());
+        assert!(parse_mpris_call("OpenUri", None, true).is_none());
+
+        let mut keys = MprisMediaKeys::new();
+        keys.update(&now_playing);
+        assert_eq!(keys.property("xesam:title"), Some(&MprisValue::Str("Song".into())));
+        keys.method_call("PlayPause", None);
+        assert_eq!(keys.poll_action().unwrap().action, MediaSessionAction::Pause);
+    }
+}
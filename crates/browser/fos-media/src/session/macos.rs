@@ -0,0 +1,83 @@
+//! Now Playing Info Center (macOS)
+//!
+//! This is a stub implementation. Full implementation would set
+//! MPNowPlayingInfoCenter.defaultCenter.nowPlayingInfo and playbackState on
+//! each update and register targets on MPRemoteCommandCenter commands that
+//! forward into [`NowPlayingMediaKeys::remote_command`].
+
+use super::{MediaKeyBackend, MediaSessionAction, MediaSessionActionDetails, NowPlaying};
+use std::collections::VecDeque;
+
+/// MPRemoteCommandCenter command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemoteCommand {
+    Play,
+    Pause,
+    TogglePlayPause,
+    Stop,
+    NextTrack,
+    PreviousTrack,
+    /// skipForwardCommand with its interval (seconds)
+    SkipForward(f64),
+    SkipBackward(f64),
+    /// changePlaybackPositionCommand (seconds)
+    ChangePlaybackPosition(f64),
+}
+
+/// Map a remote command to an action
+pub(crate) fn remote_command_action(command: RemoteCommand, playing: bool) -> MediaSessionActionDetails {
+    match command {
+        RemoteCommand::Play => MediaSessionActionDetails::new(MediaSessionAction::Play),
+        RemoteCommand::Pause => MediaSessionActionDetails::new(MediaSessionAction::Pause),
+        RemoteCommand::TogglePlayPause => MediaSessionActionDetails::toggle(playing),
+        RemoteCommand::Stop => MediaSessionActionDetails::new(MediaSessionAction::Stop),
+        RemoteCommand::NextTrack => MediaSessionActionDetails::new(MediaSessionAction::NextTrack),
+        RemoteCommand::PreviousTrack => MediaSessionActionDetails::new(MediaSessionAction::PreviousTrack),
+        RemoteCommand::SkipForward(interval) => MediaSessionActionDetails::seek_by(interval.abs()),
+        RemoteCommand::SkipBackward(interval) => MediaSessionActionDetails::seek_by(-interval.abs()),
+        RemoteCommand::ChangePlaybackPosition(position) => MediaSessionActionDetails::seek_to(position.max(0.0)),
+    }
+}
+
+/// MPNowPlayingInfoCenter integration
+#[derive(Debug, Default)]
+pub struct NowPlayingMediaKeys {
+    // In real impl: command center target handles
+    published: Option<NowPlaying>,
+    pending: VecDeque<MediaSessionActionDetails>,
+}
+
+impl NowPlayingMediaKeys {
+    pub fn new() -> Self { Self::default() }
+
+    /// A command center target fired
+    pub fn remote_command(&mut self, command: RemoteCommand) {
+        let playing = self.published.as_ref().is_some_and(|n| n.playing);
+        self.pending.push_back(remote_command_action(command, playing));
+    }
+}
+
+impl MediaKeyBackend for NowPlayingMediaKeys {
+    fn name(&self) -> &'static str { "mpnowplaying" }
+
+    fn update(&mut self, now_playing: &NowPlaying) {
+        // Real impl would:
+        // 1. nowPlayingInfo = { MPMediaItemPropertyTitle, MPMediaItemPropertyArtist,
+        //    MPMediaItemPropertyAlbumTitle, MPMediaItemPropertyArtwork,
+        //    MPMediaItemPropertyPlaybackDuration, MPNowPlayingInfoPropertyElapsedPlaybackTime,
+        //    MPNowPlayingInfoPropertyPlaybackRate }
+        // 2. playbackState = MPNowPlayingPlaybackStatePlaying / Paused
+        // 3. command.enabled for each command from now_playing.actions
+        self.published = Some(now_playing.clone());
+    }
+
+    fn clear(&mut self) {
+        // Real impl: nowPlayingInfo = nil, playbackState = Stopped
+        self.published = None;
+        self.pending.clear();
+    }
+
+    fn poll_action(&mut self) -> Option<MediaSessionActionDetails> {
+        self.pending.pop_front()
+    }
+}
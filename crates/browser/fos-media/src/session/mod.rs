@@ -0,0 +1,560 @@
+//! Media Session
+//!
+//! `navigator.mediaSession` metadata, playback and position state and action
+//! handlers, published to the OS "now playing" surface so hardware media keys
+//! and system media overlays control playback.
+//!
+//! Backends:
+//! - Linux: MPRIS (org.mpris.MediaPlayer2 on the session bus)
+//! - macOS: MPNowPlayingInfoCenter and MPRemoteCommandCenter
+//! - Windows: SystemMediaTransportControls (SMTC)
+//! - Null: records what was published and replays injected key presses (tests, headless)
+//!
+//! An action coming from the OS goes to the page when it registered a handler
+//! for it; otherwise play, pause, stop and seeking fall back to acting on the
+//! active [`HTMLMediaElement`] directly.
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+#[cfg(target_os = "linux")]
+pub use linux::MprisMediaKeys;
+#[cfg(target_os = "macos")]
+pub use macos::{NowPlayingMediaKeys, RemoteCommand};
+#[cfg(target_os = "windows")]
+pub use windows::{SmtcButton, SmtcMediaKeys};
+
+use crate::element::HTMLMediaElement;
+use std::collections::{HashSet, VecDeque};
+use std::time::Instant;
+
+/// Default seek step for seekbackward/seekforward without a seekOffset (seconds)
+pub const DEFAULT_SEEK_OFFSET: f64 = 10.0;
+
+/// Media session action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaSessionAction {
+    Play,
+    Pause,
+    Stop,
+    SeekBackward,
+    SeekForward,
+    SeekTo,
+    PreviousTrack,
+    NextTrack,
+    SkipAd,
+}
+
+impl MediaSessionAction {
+    pub const ALL: [Self; 9] = [
+        Self::Play, Self::Pause, Self::Stop, Self::SeekBackward, Self::SeekForward,
+        Self::SeekTo, Self::PreviousTrack, Self::NextTrack, Self::SkipAd,
+    ];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.as_str() == s)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Play => "play",
+            Self::Pause => "pause",
+            Self::Stop => "stop",
+            Self::SeekBackward => "seekbackward",
+            Self::SeekForward => "seekforward",
+            Self::SeekTo => "seekto",
+            Self::PreviousTrack => "previoustrack",
+            Self::NextTrack => "nexttrack",
+            Self::SkipAd => "skipad",
+        }
+    }
+}
+
+/// Artwork image
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaImage {
+    pub src: String,
+    /// e.g. "512x512"
+    pub sizes: String,
+    pub mime_type: String,
+}
+
+/// `MediaMetadata`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaMetadata {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub artwork: Vec<MediaImage>,
+}
+
+/// `MediaSessionPlaybackState`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MediaSessionPlaybackState {
+    /// Derived from the media element
+    #[default]
+    None,
+    Paused,
+    Playing,
+}
+
+/// `MediaPositionState`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaPositionState {
+    /// Seconds; may be infinite for live streams
+    pub duration: f64,
+    pub playback_rate: f64,
+    pub position: f64,
+}
+
+/// Action sent to a handler
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaSessionActionDetails {
+    pub action: MediaSessionAction,
+    /// seekbackward/seekforward step in seconds
+    pub seek_offset: Option<f64>,
+    /// seekto target in seconds
+    pub seek_time: Option<f64>,
+    /// seekto is part of a scrub; precision can be traded for speed
+    pub fast_seek: bool,
+}
+
+impl MediaSessionActionDetails {
+    pub fn new(action: MediaSessionAction) -> Self {
+        Self { action, seek_offset: None, seek_time: None, fast_seek: false }
+    }
+
+    pub fn seek_by(offset: f64) -> Self {
+        let action = if offset < 0.0 { MediaSessionAction::SeekBackward } else { MediaSessionAction::SeekForward };
+        Self { seek_offset: Some(offset.abs()), ..Self::new(action) }
+    }
+
+    pub fn seek_to(time: f64) -> Self {
+        Self { seek_time: Some(time), ..Self::new(MediaSessionAction::SeekTo) }
+    }
+
+    /// Play/pause key: pause when playing, play otherwise
+    pub fn toggle(playing: bool) -> Self {
+        Self::new(if playing { MediaSessionAction::Pause } else { MediaSessionAction::Play })
+    }
+}
+
+/// Media session error
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum MediaSessionError {
+    #[error("Invalid position state: {0}")]
+    InvalidPositionState(&'static str),
+}
+
+/// `navigator.mediaSession` of one document
+#[derive(Debug, Default)]
+pub struct MediaSession {
+    pub metadata: Option<MediaMetadata>,
+    pub playback_state: MediaSessionPlaybackState,
+    position_state: Option<(MediaPositionState, Instant)>,
+    /// Actions with a registered handler; the callbacks live in the script engine
+    handlers: HashSet<MediaSessionAction>,
+}
+
+impl MediaSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `setActionHandler(action, handler)`; `false` for a null handler
+    pub fn set_action_handler(&mut self, action: MediaSessionAction, has_handler: bool) {
+        if has_handler {
+            self.handlers.insert(action);
+        } else {
+            self.handlers.remove(&action);
+        }
+    }
+
+    pub fn has_action_handler(&self, action: MediaSessionAction) -> bool {
+        self.handlers.contains(&action)
+    }
+
+    /// `setPositionState(state)`; `None` clears it
+    pub fn set_position_state(&mut self, state: Option<MediaPositionState>) -> Result<(), MediaSessionError> {
+        let Some(state) = state else {
+            self.position_state = None;
+            return Ok(());
+        };
+        if state.duration.is_nan() || state.duration < 0.0 {
+            return Err(MediaSessionError::InvalidPositionState("duration must be non-negative"));
+        }
+        if !(0.0..=state.duration).contains(&state.position) {
+            return Err(MediaSessionError::InvalidPositionState("position must be between 0 and duration"));
+        }
+        if state.playback_rate == 0.0 || !state.playback_rate.is_finite() {
+            return Err(MediaSessionError::InvalidPositionState("playbackRate must be non-zero"));
+        }
+        self.position_state = Some((state, Instant::now()));
+        Ok(())
+    }
+
+    /// Position state with the position advanced by the time since it was set
+    pub fn position_state(&self) -> Option<MediaPositionState> {
+        let (state, set_at) = self.position_state?;
+        if self.playback_state != MediaSessionPlaybackState::Playing {
+            return Some(state);
+        }
+        let position = state.position + state.playback_rate * set_at.elapsed().as_secs_f64();
+        Some(MediaPositionState { position: position.clamp(0.0, state.duration), ..state })
+    }
+}
+
+/// What is shown on the OS now-playing surface
+#[derive(Debug, Clone, PartialEq)]
+pub struct NowPlaying {
+    pub metadata: MediaMetadata,
+    pub playing: bool,
+    pub position: Option<MediaPositionState>,
+    /// Actions the OS should offer
+    pub actions: Vec<MediaSessionAction>,
+}
+
+impl NowPlaying {
+    /// Combine a session with the element it controls
+    pub fn resolve(session: &MediaSession, element: Option<&HTMLMediaElement>) -> Self {
+        let playing = match session.playback_state {
+            MediaSessionPlaybackState::Playing => true,
+            MediaSessionPlaybackState::Paused => false,
+            MediaSessionPlaybackState::None => element.is_some_and(|e| !e.paused),
+        };
+        let position = session.position_state().or_else(|| {
+            let e = element.filter(|e| e.duration.is_finite())?;
+            Some(MediaPositionState { duration: e.duration, playback_rate: e.playback_rate, position: e.current_time })
+        });
+
+        let seekable = element.is_some_and(|e| e.duration.is_finite());
+        let actions = MediaSessionAction::ALL.into_iter().filter(|&action| {
+            session.has_action_handler(action) || match action {
+                MediaSessionAction::Play | MediaSessionAction::Pause | MediaSessionAction::Stop => element.is_some(),
+                MediaSessionAction::SeekBackward | MediaSessionAction::SeekForward | MediaSessionAction::SeekTo => seekable,
+                _ => false,
+            }
+        }).collect();
+
+        Self { metadata: session.metadata.clone().unwrap_or_default(), playing, position, actions }
+    }
+
+    /// Whether `self` differs from `previous`, published `elapsed` seconds
+    /// ago, by more than a playing position moving on
+    fn changed_since(&self, previous: &NowPlaying, elapsed: f64) -> bool {
+        if self.metadata != previous.metadata || self.playing != previous.playing || self.actions != previous.actions {
+            return true;
+        }
+        match (self.position, previous.position) {
+            (Some(now), Some(then)) => {
+                let expected = if previous.playing { then.position + then.playback_rate * elapsed } else { then.position };
+                now.duration != then.duration
+                    || now.playback_rate != then.playback_rate
+                    // A seek; small drift is left to the OS to extrapolate
+                    || (now.position - expected).abs() > 1.0
+            }
+            (None, None) => false,
+            _ => true,
+        }
+    }
+}
+
+/// OS media key / now-playing integration
+pub trait MediaKeyBackend: Send {
+    /// Backend name ("mpris", "mpnowplaying", "smtc", "null")
+    fn name(&self) -> &'static str;
+
+    /// Publish metadata, state and available actions
+    fn update(&mut self, now_playing: &NowPlaying);
+
+    /// Remove the session from the OS surface
+    fn clear(&mut self);
+
+    /// Next action requested from the OS (media key, overlay button)
+    fn poll_action(&mut self) -> Option<MediaSessionActionDetails>;
+}
+
+/// Create the preferred media key backend for this platform
+pub fn create_media_key_backend() -> Box<dyn MediaKeyBackend> {
+    #[cfg(target_os = "linux")]
+    {
+        if MprisMediaKeys::is_available() {
+            return Box::new(MprisMediaKeys::new());
+        }
+        Box::new(NullMediaKeys::new())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Box::new(NowPlayingMediaKeys::new())
+    }
+    #[cfg(target_os = "windows")]
+    {
+        Box::new(SmtcMediaKeys::new())
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        Box::new(NullMediaKeys::new())
+    }
+}
+
+/// Backend without an OS surface
+#[derive(Debug, Default)]
+pub struct NullMediaKeys {
+    now_playing: Option<NowPlaying>,
+    pending: VecDeque<MediaSessionActionDetails>,
+    updates: u64,
+}
+
+impl NullMediaKeys {
+    pub fn new() -> Self { Self::default() }
+
+    /// Simulate a media key press
+    pub fn press(&mut self, details: MediaSessionActionDetails) {
+        self.pending.push_back(details);
+    }
+
+    pub fn now_playing(&self) -> Option<&NowPlaying> { self.now_playing.as_ref() }
+
+    /// Number of updates published
+    pub fn updates(&self) -> u64 { self.updates }
+}
+
+impl MediaKeyBackend for NullMediaKeys {
+    fn name(&self) -> &'static str { "null" }
+
+    fn update(&mut self, now_playing: &NowPlaying) {
+        self.now_playing = Some(now_playing.clone());
+        self.updates += 1;
+    }
+
+    fn clear(&mut self) {
+        self.now_playing = None;
+    }
+
+    fn poll_action(&mut self) -> Option<MediaSessionActionDetails> {
+        self.pending.pop_front()
+    }
+}
+
+/// How an action was handled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActionRoute {
+    /// Dispatch to the page's handler
+    Handler(MediaSessionActionDetails),
+    /// Applied to the media element by the default behaviour
+    Default,
+    /// No handler and no default behaviour
+    Ignored,
+}
+
+/// Connects the active media session to the OS media keys
+pub struct MediaSessionController {
+    backend: Box<dyn MediaKeyBackend>,
+    published: Option<(NowPlaying, Instant)>,
+}
+
+impl std::fmt::Debug for MediaSessionController {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MediaSessionController")
+            .field("backend", &self.backend.name())
+            .field("published", &self.published)
+            .finish()
+    }
+}
+
+impl MediaSessionController {
+    /// Controller on the platform's preferred backend
+    pub fn new() -> Self {
+        Self::with_backend(create_media_key_backend())
+    }
+
+    pub fn with_backend(backend: Box<dyn MediaKeyBackend>) -> Self {
+        Self { backend, published: None }
+    }
+
+    pub fn backend_name(&self) -> &'static str { self.backend.name() }
+
+    pub fn now_playing(&self) -> Option<&NowPlaying> {
+        self.published.as_ref().map(|(n, _)| n)
+    }
+
+    /// Publish the session's state if it changed
+    pub fn sync(&mut self, session: &MediaSession, element: Option<&HTMLMediaElement>) {
+        let now_playing = NowPlaying::resolve(session, element);
+        let changed = self.published.as_ref()
+            .is_none_or(|(prev, at)| now_playing.changed_since(prev, at.elapsed().as_secs_f64()));
+        if changed {
+            self.backend.update(&now_playing);
+            self.published = Some((now_playing, Instant::now()));
+        }
+    }
+
+    /// Remove the session from the OS (no active media left)
+    pub fn clear(&mut self) {
+        if self.published.take().is_some() {
+            self.backend.clear();
+        }
+    }
+
+    /// Route one action to the page or the element's default behaviour
+    pub fn handle_action(
+        &mut self,
+        session: &MediaSession,
+        element: Option<&mut HTMLMediaElement>,
+        details: MediaSessionActionDetails,
+    ) -> ActionRoute {
+        if session.has_action_handler(details.action) {
+            return ActionRoute::Handler(details);
+        }
+        let Some(element) = element else { return ActionRoute::Ignored };
+        let seekable = element.duration.is_finite();
+        match details.action {
+            MediaSessionAction::Play => {
+                if element.play().is_err() {
+                    return ActionRoute::Ignored;
+                }
+            }
+            MediaSessionAction::Pause => element.pause(),
+            MediaSessionAction::Stop => {
+                element.pause();
+                if seekable {
+                    element.seek(0.0);
+                }
+            }
+            MediaSessionAction::SeekBackward | MediaSessionAction::SeekForward if seekable => {
+                let offset = details.seek_offset.unwrap_or(DEFAULT_SEEK_OFFSET);
+                let sign = if details.action == MediaSessionAction::SeekBackward { -1.0 } else { 1.0 };
+                element.seek(element.current_time + sign * offset);
+            }
+            MediaSessionAction::SeekTo if seekable => {
+                let Some(time) = details.seek_time else { return ActionRoute::Ignored };
+                if details.fast_seek {
+                    element.fast_seek(time);
+                } else {
+                    element.seek(time);
+                }
+            }
+            _ => return ActionRoute::Ignored,
+        }
+        ActionRoute::Default
+    }
+
+    /// Drain OS actions; returns those to dispatch to page handlers
+    pub fn poll(
+        &mut self,
+        session: &MediaSession,
+        mut element: Option<&mut HTMLMediaElement>,
+    ) -> Vec<MediaSessionActionDetails> {
+        let mut dispatch = Vec::new();
+        while let Some(details) = self.backend.poll_action() {
+            if let ActionRoute::Handler(details) = self.handle_action(session, element.as_deref_mut(), details) {
+                dispatch.push(details);
+            }
+        }
+        if !dispatch.is_empty() || element.is_some() {
+            self.sync(session, element.as_deref());
+        }
+        dispatch
+    }
+}
+
+impl Default for MediaSessionController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::ReadyState;
+    use std::sync::{Arc, Mutex};
+
+    /// Null backend shared with the test
+    #[derive(Clone, Default)]
+    struct SharedKeys(Arc<Mutex<NullMediaKeys>>);
+
+    impl MediaKeyBackend for SharedKeys {
+        fn name(&self) -> &'static str { "null" }
+        fn update(&mut self, now_playing: &NowPlaying) { self.0.lock().unwrap().update(now_playing) }
+        fn clear(&mut self) { self.0.lock().unwrap().clear() }
+        fn poll_action(&mut self) -> Option<MediaSessionActionDetails> { self.0.lock().unwrap().poll_action() }
+    }
+
+    fn element() -> HTMLMediaElement {
+        let mut element = HTMLMediaElement::new();
+        element.ready_state = ReadyState::HaveEnoughData;
+        element.duration = 120.0;
+        element
+    }
+
+    #[test]
+    fn test_position_state_validation() {
+        let mut session = MediaSession::new();
+        let state = MediaPositionState { duration: 60.0, playback_rate: 1.0, position: 10.0 };
+        session.set_position_state(Some(state)).unwrap();
+        assert_eq!(session.position_state(), Some(state));
+        assert!(session.set_position_state(Some(MediaPositionState { position: 61.0, ..state })).is_err());
+        assert!(session.set_position_state(Some(MediaPositionState { playback_rate: 0.0, ..state })).is_err());
+        assert!(session.set_position_state(Some(MediaPositionState { duration: f64::INFINITY, ..state })).is_ok());
+        assert_eq!(MediaSessionAction::parse("seekto"), Some(MediaSessionAction::SeekTo));
+    }
+
+    #[test]
+    fn test_default_actions_control_element() {
+        let keys = SharedKeys::default();
+        let mut controller = MediaSessionController::with_backend(Box::new(keys.clone()));
+        let mut session = MediaSession::new();
+        session.metadata = Some(MediaMetadata { title: "Song".into(), artist: "Band".into(), ..Default::default() });
+        let mut media = element();
+
+        controller.sync(&session, Some(&media));
+        let published = keys.0.lock().unwrap().now_playing().cloned().unwrap();
+        assert_eq!(published.metadata.title, "Song");
+        assert!(!published.playing);
+        assert!(published.actions.contains(&MediaSessionAction::SeekTo));
+        assert!(!published.actions.contains(&MediaSessionAction::NextTrack));
+
+        let toggle = MediaSessionActionDetails::toggle(controller.now_playing().is_some_and(|n| n.playing));
+        keys.0.lock().unwrap().press(toggle);
+        keys.0.lock().unwrap().press(MediaSessionActionDetails::seek_by(30.0));
+        assert!(controller.poll(&session, Some(&mut media)).is_empty());
+        assert!(!media.paused);
+        assert_eq!(media.current_time, 30.0);
+        assert!(controller.now_playing().unwrap().playing);
+
+        // Unchanged state is not republished
+        let updates = keys.0.lock().unwrap().updates();
+        controller.sync(&session, Some(&media));
+        assert_eq!(keys.0.lock().unwrap().updates(), updates);
+
+        controller.clear();
+        assert!(keys.0.lock().unwrap().now_playing().is_none());
+    }
+
+    #[test]
+    fn test_page_handlers_take_precedence() {
+        let mut controller = MediaSessionController::with_backend(Box::new(NullMediaKeys::new()));
+        let mut session = MediaSession::new();
+        session.set_action_handler(MediaSessionAction::NextTrack, true);
+        session.set_action_handler(MediaSessionAction::Pause, true);
+        let mut media = element();
+        media.play().unwrap();
+
+        let next = MediaSessionActionDetails::new(MediaSessionAction::NextTrack);
+        assert_eq!(controller.handle_action(&session, Some(&mut media), next), ActionRoute::Handler(next));
+        let pause = MediaSessionActionDetails::new(MediaSessionAction::Pause);
+        assert_eq!(controller.handle_action(&session, Some(&mut media), pause), ActionRoute::Handler(pause));
+        assert!(!media.paused);
+
+        let skip = MediaSessionActionDetails::new(MediaSessionAction::SkipAd);
+        assert_eq!(controller.handle_action(&session, Some(&mut media), skip), ActionRoute::Ignored);
+        let stop = MediaSessionActionDetails::new(MediaSessionAction::Stop);
+        assert_eq!(controller.handle_action(&session, Some(&mut media), stop), ActionRoute::Default);
+        assert!(media.paused);
+    }
+}
@@ -0,0 +1,89 @@
+//! System Media Transport Controls (Windows)
+//!
+//! This is a stub implementation. Full implementation would get the SMTC
+//! instance for the browser window through
+//! ISystemMediaTransportControlsInterop::GetForWindow, fill its
+//! DisplayUpdater and timeline properties on each update and forward
+//! ButtonPressed / PlaybackPositionChangeRequested events into
+//! [`SmtcMediaKeys::button_pressed`] and [`SmtcMediaKeys::position_change_requested`].
+
+use super::{MediaKeyBackend, MediaSessionAction, MediaSessionActionDetails, NowPlaying};
+use std::collections::VecDeque;
+
+/// SystemMediaTransportControlsButton
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtcButton {
+    Play,
+    Pause,
+    Stop,
+    Record,
+    FastForward,
+    Rewind,
+    Next,
+    Previous,
+    ChannelUp,
+    ChannelDown,
+}
+
+/// Map a button press to an action
+pub(crate) fn smtc_button_action(button: SmtcButton) -> Option<MediaSessionActionDetails> {
+    Some(match button {
+        SmtcButton::Play => MediaSessionActionDetails::new(MediaSessionAction::Play),
+        SmtcButton::Pause => MediaSessionActionDetails::new(MediaSessionAction::Pause),
+        SmtcButton::Stop => MediaSessionActionDetails::new(MediaSessionAction::Stop),
+        SmtcButton::Next => MediaSessionActionDetails::new(MediaSessionAction::NextTrack),
+        SmtcButton::Previous => MediaSessionActionDetails::new(MediaSessionAction::PreviousTrack),
+        SmtcButton::FastForward => MediaSessionActionDetails::new(MediaSessionAction::SeekForward),
+        SmtcButton::Rewind => MediaSessionActionDetails::new(MediaSessionAction::SeekBackward),
+        SmtcButton::Record | SmtcButton::ChannelUp | SmtcButton::ChannelDown => return None,
+    })
+}
+
+/// SMTC integration for the browser window
+#[derive(Debug, Default)]
+pub struct SmtcMediaKeys {
+    // In real impl: SystemMediaTransportControls, event registration tokens
+    published: Option<NowPlaying>,
+    pending: VecDeque<MediaSessionActionDetails>,
+}
+
+impl SmtcMediaKeys {
+    pub fn new() -> Self { Self::default() }
+
+    /// ButtonPressed event
+    pub fn button_pressed(&mut self, button: SmtcButton) {
+        if let Some(details) = smtc_button_action(button) {
+            self.pending.push_back(details);
+        }
+    }
+
+    /// PlaybackPositionChangeRequested event (seconds)
+    pub fn position_change_requested(&mut self, position: f64) {
+        self.pending.push_back(MediaSessionActionDetails::seek_to(position.max(0.0)));
+    }
+}
+
+impl MediaKeyBackend for SmtcMediaKeys {
+    fn name(&self) -> &'static str { "smtc" }
+
+    fn update(&mut self, now_playing: &NowPlaying) {
+        // Real impl would:
+        // 1. IsEnabled = true; IsPlayEnabled/IsPauseEnabled/IsStopEnabled/IsNextEnabled/
+        //    IsPreviousEnabled/IsFastForwardEnabled/IsRewindEnabled from now_playing.actions
+        // 2. PlaybackStatus = Playing / Paused
+        // 3. DisplayUpdater: Type = Music, MusicProperties Title/Artist/AlbumTitle,
+        //    Thumbnail = RandomAccessStreamReference::CreateFromUri(artwork), Update()
+        // 4. UpdateTimelineProperties(StartTime 0, EndTime duration, Position)
+        self.published = Some(now_playing.clone());
+    }
+
+    fn clear(&mut self) {
+        // Real impl: DisplayUpdater.ClearAll(), IsEnabled = false
+        self.published = None;
+        self.pending.clear();
+    }
+
+    fn poll_action(&mut self) -> Option<MediaSessionActionDetails> {
+        self.pending.pop_front()
+    }
+}
@@ -1,11 +1,12 @@
 //! Streaming Protocols
 //!
-//! HLS, DASH, and adaptive bitrate streaming.
+//! HLS, DASH, adaptive bitrate streaming and progressive download.
 
 pub mod hls;
 pub mod dash;
 pub mod abr;
 pub mod buffer;
+pub mod progressive;
 
 use std::time::Duration;
 
@@ -0,0 +1,938 @@
+//! Progressive Download
+//!
+//! Plays a media file while it downloads, as `<audio src>` does. The file is
+//! fetched in byte ranges. A seek index read from the start of the file (MP3
+//! frame header and Xing table of contents, Ogg headers and last page, MP4
+//! `moov`) maps times to byte offsets, so seeking fetches from the right
+//! place and the downloaded byte ranges can be reported as `buffered` times.
+
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::containers::mp4::Mp4Demuxer;
+use crate::containers::Demuxer;
+use crate::element::{HTMLMediaElement, MediaError, MediaErrorCode, NetworkState, ReadyState, TimeRanges};
+
+/// Bytes fetched per range request
+const CHUNK_SIZE: u64 = 256 * 1024;
+/// Bytes read from the end of an Ogg file to find its last granule position
+const OGG_TAIL: u64 = 64 * 1024;
+/// Fetching suspends with this many seconds buffered ahead of playback
+const MAX_BUFFER_AHEAD: f64 = 60.0;
+/// Seconds buffered ahead of playback for `HAVE_FUTURE_DATA`
+const FUTURE_DATA_AHEAD: f64 = 2.0;
+/// Minimum interval between `progress` events (spec: 350 ms)
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(350);
+/// Ogg seeks stop bisecting within this many seconds before the target
+const OGG_SEEK_TOLERANCE: f64 = 1.0;
+const OGG_SEEK_STEPS: u32 = 16;
+
+/// Container of a progressively played file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressiveFormat {
+    Mp3,
+    Ogg,
+    Mp4,
+}
+
+impl ProgressiveFormat {
+    /// Format from the first bytes of the file
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"ID3") || mp3_frame(data).is_some() {
+            Some(Self::Mp3)
+        } else if data.starts_with(b"OggS") {
+            Some(Self::Ogg)
+        } else if data.get(4..8) == Some(b"ftyp") {
+            Some(Self::Mp4)
+        } else {
+            None
+        }
+    }
+}
+
+/// Events for the media element to fire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadEvent {
+    LoadStart,
+    Progress,
+    Suspend,
+    Error,
+    DurationChange,
+    LoadedMetadata,
+    LoadedData,
+    CanPlay,
+    CanPlayThrough,
+    Playing,
+    Waiting,
+}
+
+impl LoadEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LoadStart => "loadstart",
+            Self::Progress => "progress",
+            Self::Suspend => "suspend",
+            Self::Error => "error",
+            Self::DurationChange => "durationchange",
+            Self::LoadedMetadata => "loadedmetadata",
+            Self::LoadedData => "loadeddata",
+            Self::CanPlay => "canplay",
+            Self::CanPlayThrough => "canplaythrough",
+            Self::Playing => "playing",
+            Self::Waiting => "waiting",
+        }
+    }
+}
+
+/// Downloaded parts of the file as sorted, disjoint runs
+#[derive(Debug, Default)]
+struct ByteRuns {
+    runs: Vec<(u64, Vec<u8>)>,
+}
+
+impl ByteRuns {
+    fn insert(&mut self, offset: u64, data: &[u8]) {
+        let end = offset + data.len() as u64;
+        // Runs overlapping or touching `offset..end` merge with it
+        let first = self.runs.partition_point(|(s, d)| s + (d.len() as u64) < offset);
+        let last = self.runs.partition_point(|(s, _)| *s <= end);
+        let (mut start, mut stop) = (offset, end);
+        if first < last {
+            start = start.min(self.runs[first].0);
+            let (s, d) = &self.runs[last - 1];
+            stop = stop.max(s + d.len() as u64);
+        }
+        let mut merged = vec![0; (stop - start) as usize];
+        for (s, d) in &self.runs[first..last] {
+            merged[(s - start) as usize..][..d.len()].copy_from_slice(d);
+        }
+        merged[(offset - start) as usize..][..data.len()].copy_from_slice(data);
+        self.runs.splice(first..last, [(start, merged)]);
+    }
+
+    /// Downloaded bytes from `offset` up to the next gap
+    fn contiguous(&self, offset: u64) -> &[u8] {
+        let i = self.runs.partition_point(|(s, _)| *s <= offset);
+        match i.checked_sub(1).map(|i| &self.runs[i]) {
+            Some((s, d)) if offset - s < d.len() as u64 => &d[(offset - s) as usize..],
+            _ => &[],
+        }
+    }
+
+    /// First part of `range` not downloaded yet
+    fn missing(&self, range: Range<u64>) -> Option<Range<u64>> {
+        let start = range.start + self.contiguous(range.start).len() as u64;
+        if start >= range.end {
+            return None;
+        }
+        let next = self.runs.iter().map(|(s, _)| *s).find(|&s| s > start).unwrap_or(u64::MAX);
+        Some(start..range.end.min(next))
+    }
+
+    fn ranges(&self) -> impl Iterator<Item = Range<u64>> + '_ {
+        self.runs.iter().map(|(s, d)| *s..s + d.len() as u64)
+    }
+}
+
+/// MPEG audio Layer III frame header
+#[derive(Debug, Clone, Copy)]
+struct Mp3Frame {
+    sample_rate: u32,
+    bitrate: u32,
+    samples: u32,
+    /// Side information length, which the Xing header follows
+    side_info: usize,
+}
+
+fn mp3_frame(data: &[u8]) -> Option<Mp3Frame> {
+    const MPEG1_KBPS: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+    const MPEG2_KBPS: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    let h = data.get(..4)?;
+    if h[0] != 0xFF || h[1] & 0xE0 != 0xE0 || (h[1] >> 1) & 3 != 1 {
+        return None;
+    }
+    let version = (h[1] >> 3) & 3;
+    let mpeg1 = version == 3;
+    let bitrate = match (h[2] >> 4) as usize {
+        0 | 15 => return None,
+        i if mpeg1 => MPEG1_KBPS[i],
+        i => MPEG2_KBPS[i],
+    };
+    let base_rate = [44100, 48000, 32000].get(((h[2] >> 2) & 3) as usize)?;
+    let sample_rate = match version {
+        3 => *base_rate,
+        2 => base_rate / 2,
+        0 => base_rate / 4,
+        _ => return None,
+    };
+    let mono = h[3] >> 6 == 3;
+    Some(Mp3Frame {
+        sample_rate,
+        bitrate: bitrate * 1000,
+        samples: if mpeg1 { 1152 } else { 576 },
+        side_info: match (mpeg1, mono) {
+            (true, false) => 32,
+            (true, true) | (false, false) => 17,
+            (false, true) => 9,
+        },
+    })
+}
+
+/// Length of the ID3v2 tag at the start of `data`, if any
+fn id3_length(data: &[u8]) -> Option<u64> {
+    if !data.starts_with(b"ID3") {
+        return None;
+    }
+    let h = data.get(..10)?;
+    let size = h[6..10].iter().fold(0u64, |n, &b| (n << 7) | (b & 0x7F) as u64);
+    let footer = if h[5] & 0x10 != 0 { 10 } else { 0 };
+    Some(10 + size + footer)
+}
+
+/// Complete Ogg page at the start of `data`: (granule position, page length)
+fn ogg_page(data: &[u8]) -> Option<(i64, usize)> {
+    let header = data.get(..27)?;
+    if &header[..4] != b"OggS" {
+        return None;
+    }
+    let segments = header[26] as usize;
+    let body: usize = data.get(27..27 + segments)?.iter().map(|&b| b as usize).sum();
+    let granule = i64::from_le_bytes(header[6..14].try_into().ok()?);
+    let len = 27 + segments + body;
+    (data.len() >= len).then_some((granule, len))
+}
+
+/// First page in `data` that ends a packet: (offset, granule position,
+/// page length)
+fn find_ogg_page(data: &[u8]) -> Option<(usize, i64, usize)> {
+    (0..data.len().saturating_sub(3))
+        .filter(|&i| &data[i..i + 4] == b"OggS")
+        .find_map(|i| ogg_page(&data[i..]).filter(|&(granule, _)| granule >= 0).map(|(granule, len)| (i, granule, len)))
+}
+
+/// Maps between media time and byte offsets
+#[derive(Debug, Clone)]
+enum SeekIndex {
+    /// Constant bitrate, or the Xing table of contents: byte position of
+    /// each percent of the duration, in 256ths of the data
+    Mp3 { data: Range<u64>, bytes_per_second: f64, duration: f64, toc: Option<Vec<u8>> },
+    /// Offsets are interpolated, and seeks refined by bisection
+    Ogg { data: Range<u64>, granule_rate: f64, pre_skip: i64, duration: f64 },
+    /// Audio samples: (start time, offset, end offset)
+    Mp4 { samples: Vec<(f64, u64, u64)>, duration: f64 },
+}
+
+impl SeekIndex {
+    fn duration(&self) -> f64 {
+        match self {
+            Self::Mp3 { duration, .. } | Self::Ogg { duration, .. } | Self::Mp4 { duration, .. } => *duration,
+        }
+    }
+
+    fn byte_for_time(&self, time: f64) -> u64 {
+        let time = time.max(0.0);
+        match self {
+            Self::Mp3 { data, duration, toc: Some(toc), .. } => {
+                let percent = (time / duration * 100.0).min(100.0);
+                let i = (percent as usize).min(99);
+                let next = toc.get(i + 1).map_or(256.0, |&b| b as f64);
+                let scaled = toc[i] as f64 + (next - toc[i] as f64) * (percent - i as f64);
+                data.start + (scaled / 256.0 * (data.end - data.start) as f64) as u64
+            }
+            Self::Mp3 { data, bytes_per_second, .. } => {
+                (data.start + (time * bytes_per_second) as u64).min(data.end)
+            }
+            Self::Ogg { data, duration, .. } => {
+                data.start + ((time / duration).min(1.0) * (data.end - data.start) as f64) as u64
+            }
+            Self::Mp4 { samples, .. } => {
+                let i = samples.partition_point(|s| s.0 <= time).saturating_sub(1);
+                samples.get(i).map_or(0, |s| s.1)
+            }
+        }
+    }
+
+    fn time_for_byte(&self, byte: u64) -> f64 {
+        match self {
+            Self::Mp3 { data, duration, toc: Some(toc), .. } => {
+                let scaled = byte.saturating_sub(data.start) as f64 / (data.end - data.start).max(1) as f64 * 256.0;
+                let i = toc.partition_point(|&b| b as f64 <= scaled).clamp(1, 100) - 1;
+                let (low, high) = (toc[i] as f64, toc.get(i + 1).map_or(256.0, |&b| b as f64));
+                let fraction = if high > low { ((scaled - low) / (high - low)).min(1.0) } else { 0.0 };
+                ((i as f64 + fraction) / 100.0 * duration).min(*duration)
+            }
+            Self::Mp3 { data, bytes_per_second, duration, .. } => {
+                (byte.saturating_sub(data.start) as f64 / bytes_per_second).min(*duration)
+            }
+            Self::Ogg { data, duration, .. } => {
+                let fraction = byte.saturating_sub(data.start) as f64 / (data.end - data.start).max(1) as f64;
+                fraction.min(1.0) * duration
+            }
+            Self::Mp4 { samples, duration } => {
+                // Playable up to the first sample not completely downloaded
+                let i = samples.partition_point(|s| s.2 <= byte);
+                samples.get(i).map_or(*duration, |s| s.0)
+            }
+        }
+    }
+
+    /// End of the media data
+    fn end(&self) -> u64 {
+        match self {
+            Self::Mp3 { data, .. } | Self::Ogg { data, .. } => data.end,
+            Self::Mp4 { samples, .. } => samples.iter().map(|s| s.2).max().unwrap_or(0),
+        }
+    }
+}
+
+/// Ogg seek in progress: the target lies between `low` and `high`
+#[derive(Debug, Clone, Copy)]
+struct OggSeek {
+    target: f64,
+    /// Page boundary and the time there
+    low: (u64, f64),
+    high: u64,
+    guess: u64,
+    steps: u32,
+}
+
+/// Progressive loader of a media element's resource
+///
+/// The browser fetches the ranges from [`next_request`](Self::next_request)
+/// and hands the bytes to [`on_data`](Self::on_data); [`update`](Self::update)
+/// then brings the element's state up to date and returns events to fire.
+#[derive(Debug)]
+pub struct ProgressiveLoader {
+    data: ByteRuns,
+    /// Resource length from `Content-Range`; `None` for endless streams
+    total: Option<u64>,
+    format: Option<ProgressiveFormat>,
+    index: Option<SeekIndex>,
+    /// Ogg granule rate and pre-skip, until the last page gives the duration
+    ogg_header: Option<(f64, i64)>,
+    /// Next top-level MP4 box to look at while looking for `moov`
+    mp4_box: u64,
+    /// Playback time and byte offset known to match, moved by seeks
+    anchor: (f64, u64),
+    /// Byte offset of the playback position
+    playback: u64,
+    ogg_seek: Option<OggSeek>,
+    in_flight: Option<Range<u64>>,
+    started: Duration,
+    received: u64,
+    last_progress: Option<Duration>,
+    progress_pending: bool,
+    suspended: bool,
+    error: Option<MediaErrorCode>,
+    failed: bool,
+    data_loaded: bool,
+    events: Vec<LoadEvent>,
+}
+
+impl ProgressiveLoader {
+    /// Start loading the element's resource
+    pub fn new(media: &mut HTMLMediaElement, now: Duration) -> Self {
+        media.load();
+        media.current_src = media.src.clone();
+        media.duration = f64::NAN;
+        media.error = None;
+        Self {
+            data: ByteRuns::default(),
+            total: None,
+            format: None,
+            index: None,
+            ogg_header: None,
+            mp4_box: 0,
+            anchor: (0.0, 0),
+            playback: 0,
+            ogg_seek: None,
+            in_flight: None,
+            started: now,
+            received: 0,
+            last_progress: None,
+            progress_pending: false,
+            suspended: false,
+            error: None,
+            failed: false,
+            data_loaded: false,
+            events: vec![LoadEvent::LoadStart],
+        }
+    }
+
+    pub fn format(&self) -> Option<ProgressiveFormat> {
+        self.format
+    }
+
+    /// Byte range to fetch next (`Range: bytes=start-(end-1)`), if any. Only
+    /// one range is handed out until its data or an error arrives.
+    pub fn next_request(&mut self) -> Option<Range<u64>> {
+        if self.in_flight.is_some() || self.failed {
+            return None;
+        }
+        let total = self.total.unwrap_or(u64::MAX);
+        let range = self.wanted().map(|r| r.start..r.end.min(total)).filter(|r| !r.is_empty())?;
+        self.in_flight = Some(range.clone());
+        Some(range)
+    }
+
+    /// Response to a range request: the bytes at `offset` and the resource
+    /// length, if the server reported one
+    pub fn on_data(&mut self, offset: u64, data: &[u8], total: Option<u64>) {
+        self.in_flight = None;
+        if total.is_some() {
+            self.total = total;
+        }
+        self.data.insert(offset, data);
+        self.received += data.len() as u64;
+        self.progress_pending = true;
+        if self.index.is_none() {
+            self.read_metadata();
+        }
+        self.advance_ogg_seek();
+    }
+
+    /// The fetch failed
+    pub fn on_error(&mut self) {
+        self.in_flight = None;
+        self.error = Some(MediaErrorCode::Network);
+    }
+
+    /// `currentTime = time`; the fetch in flight may be aborted
+    pub fn seek(&mut self, media: &mut HTMLMediaElement, time: f64) {
+        let Some(index) = &self.index else { return };
+        media.seek(time);
+        let time = media.current_time;
+        self.in_flight = None;
+        match index {
+            SeekIndex::Ogg { data, .. } => {
+                let guess = index.byte_for_time(time);
+                self.ogg_seek = Some(OggSeek { target: time, low: (data.start, 0.0), high: data.end, guess, steps: 0 });
+                self.advance_ogg_seek();
+            }
+            _ => {
+                self.anchor = (time, index.byte_for_time(time));
+                self.playback = self.anchor.1;
+            }
+        }
+    }
+
+    /// Bring the element's state in line with the download, returning the
+    /// events to fire
+    pub fn update(&mut self, media: &mut HTMLMediaElement, now: Duration) -> Vec<LoadEvent> {
+        if let Some(code) = self.error.take() {
+            self.failed = true;
+            let not_supported = code == MediaErrorCode::SrcNotSupported;
+            media.error = Some(MediaError {
+                code,
+                message: if not_supported { "Unsupported media format".into() } else { "Media fetch failed".into() },
+            });
+            media.network_state = if not_supported { NetworkState::NoSource } else { NetworkState::Idle };
+            self.events.push(LoadEvent::Error);
+        }
+        if self.failed {
+            return std::mem::take(&mut self.events);
+        }
+        let Some(index) = &self.index else {
+            return std::mem::take(&mut self.events);
+        };
+
+        if media.ready_state == ReadyState::HaveNothing {
+            media.duration = index.duration();
+            media.seekable = TimeRanges::new();
+            if index.duration().is_finite() {
+                media.seekable.add(0.0, index.duration());
+            }
+            media.ready_state = ReadyState::HaveMetadata;
+            self.anchor = (0.0, index.byte_for_time(0.0));
+            self.events.extend([LoadEvent::DurationChange, LoadEvent::LoadedMetadata]);
+        }
+
+        if self.progress_pending && self.last_progress.is_none_or(|last| now.saturating_sub(last) >= PROGRESS_INTERVAL) {
+            self.progress_pending = false;
+            self.last_progress = Some(now);
+            media.buffered = self.buffered();
+            self.events.push(LoadEvent::Progress);
+        }
+
+        let ready_state = self.ready_state(media, now);
+        let old = media.ready_state;
+        media.ready_state = ready_state;
+        if old < ReadyState::HaveCurrentData && ready_state >= ReadyState::HaveCurrentData && !self.data_loaded {
+            self.data_loaded = true;
+            self.events.push(LoadEvent::LoadedData);
+        }
+        if old < ReadyState::HaveFutureData && ready_state >= ReadyState::HaveFutureData {
+            self.events.push(LoadEvent::CanPlay);
+            if !media.paused {
+                self.events.push(LoadEvent::Playing);
+            }
+        }
+        if old < ReadyState::HaveEnoughData && ready_state == ReadyState::HaveEnoughData {
+            self.events.push(LoadEvent::CanPlayThrough);
+        }
+        if old >= ReadyState::HaveFutureData && ready_state < ReadyState::HaveFutureData && !media.paused {
+            self.events.push(LoadEvent::Waiting);
+        }
+
+        // Fetching stops once enough is buffered ahead or the file is complete
+        let idle = self.in_flight.is_none() && self.wanted().is_none();
+        if idle && !self.suspended {
+            media.network_state = NetworkState::Idle;
+            if self.progress_pending {
+                self.progress_pending = false;
+                self.last_progress = Some(now);
+                media.buffered = self.buffered();
+                self.events.push(LoadEvent::Progress);
+            }
+            self.events.push(LoadEvent::Suspend);
+        } else if !idle {
+            media.network_state = NetworkState::Loading;
+        }
+        self.suspended = idle;
+        std::mem::take(&mut self.events)
+    }
+
+    /// Downloaded bytes from the playback position on, for the demuxer
+    pub fn playback_data(&self) -> &[u8] {
+        self.data.contiguous(self.playback)
+    }
+
+    /// Downloaded byte ranges as media times
+    pub fn buffered(&self) -> TimeRanges {
+        let mut ranges = TimeRanges::new();
+        let Some(index) = &self.index else { return ranges };
+        let mut last: Option<(f64, f64)> = None;
+        for range in self.data.ranges() {
+            let (start, end) = (index.time_for_byte(range.start), index.time_for_byte(range.end));
+            if end <= start {
+                continue;
+            }
+            match last.as_mut() {
+                Some((_, last_end)) if start <= *last_end => *last_end = last_end.max(end),
+                _ => {
+                    if let Some((s, e)) = last.replace((start, end)) {
+                        ranges.add(s, e);
+                    }
+                }
+            }
+        }
+        if let Some((s, e)) = last {
+            ranges.add(s, e);
+        }
+        ranges
+    }
+
+    fn ready_state(&mut self, media: &HTMLMediaElement, now: Duration) -> ReadyState {
+        let Some(index) = &self.index else { return ReadyState::HaveNothing };
+        if self.ogg_seek.is_some() {
+            return ReadyState::HaveMetadata;
+        }
+        // Offsets move along with the index from the last seek's position
+        let (anchor_time, anchor_byte) = self.anchor;
+        self.playback = (anchor_byte + index.byte_for_time(media.current_time)).saturating_sub(index.byte_for_time(anchor_time));
+        let buffered_end = self.playback + self.data.contiguous(self.playback).len() as u64;
+        let end = index.end().min(self.total.unwrap_or(u64::MAX));
+        if buffered_end >= end {
+            return ReadyState::HaveEnoughData;
+        }
+        let ahead = index.time_for_byte(buffered_end) - index.time_for_byte(self.playback);
+        if ahead <= 0.0 {
+            return ReadyState::HaveMetadata;
+        }
+        if ahead < FUTURE_DATA_AHEAD {
+            return ReadyState::HaveCurrentData;
+        }
+        // Enough when the rest downloads at the rate so far before playback
+        // reaches it
+        let elapsed = now.saturating_sub(self.started).as_secs_f64();
+        let rate = if elapsed > 0.0 { self.received as f64 / elapsed } else { 0.0 };
+        let remaining_time = (index.duration() - media.current_time) / media.playback_rate.max(f64::MIN_POSITIVE);
+        if rate > 0.0 && (end - buffered_end) as f64 / rate <= remaining_time {
+            ReadyState::HaveEnoughData
+        } else {
+            ReadyState::HaveFutureData
+        }
+    }
+
+    /// Range the loader needs next, before clamping to the resource length
+    fn wanted(&self) -> Option<Range<u64>> {
+        let chunk = |start: u64| start..start.saturating_add(CHUNK_SIZE);
+        let Some(format) = self.format else {
+            return self.data.missing(chunk(0));
+        };
+        let Some(index) = &self.index else {
+            return match format {
+                ProgressiveFormat::Mp3 => {
+                    let start = id3_length(self.data.contiguous(0)).unwrap_or(0);
+                    self.data.missing(chunk(start))
+                }
+                ProgressiveFormat::Ogg => {
+                    let total = self.total?;
+                    self.data.missing(total.saturating_sub(OGG_TAIL)..total)
+                }
+                ProgressiveFormat::Mp4 => {
+                    let header = self.data.contiguous(self.mp4_box);
+                    match (header.get(4..8), header.get(..4)) {
+                        (Some(b"moov"), Some(size)) => {
+                            let size = u32::from_be_bytes(size.try_into().ok()?) as u64;
+                            self.data.missing(self.mp4_box..self.mp4_box + size)
+                        }
+                        _ => self.data.missing(chunk(self.mp4_box)),
+                    }
+                }
+            };
+        };
+        if let Some(seek) = &self.ogg_seek {
+            return self.data.missing(chunk(seek.guess));
+        }
+        let end = index.end().min(self.total.unwrap_or(u64::MAX));
+        let buffered_end = self.playback + self.data.contiguous(self.playback).len() as u64;
+        let ahead = index.time_for_byte(buffered_end) - index.time_for_byte(self.playback);
+        if buffered_end >= end || ahead >= MAX_BUFFER_AHEAD {
+            return None;
+        }
+        Some(chunk(buffered_end))
+    }
+
+    fn read_metadata(&mut self) {
+        if self.format.is_none() {
+            let head = self.data.contiguous(0);
+            if head.len() < 12 && self.total.is_none_or(|t| t > head.len() as u64) {
+                return;
+            }
+            self.format = ProgressiveFormat::detect(head);
+            if self.format.is_none() {
+                self.error = Some(MediaErrorCode::SrcNotSupported);
+                return;
+            }
+        }
+        self.index = match self.format {
+            Some(ProgressiveFormat::Mp3) => self.mp3_index(),
+            Some(ProgressiveFormat::Ogg) => self.ogg_index(),
+            Some(ProgressiveFormat::Mp4) => self.mp4_index(),
+            None => None,
+        };
+    }
+
+    fn mp3_index(&mut self) -> Option<SeekIndex> {
+        let start = id3_length(self.data.contiguous(0)).unwrap_or(0);
+        let data = self.data.contiguous(start);
+        if data.len() < 4 {
+            return None;
+        }
+        let Some(frame) = mp3_frame(data) else {
+            self.error = Some(MediaErrorCode::SrcNotSupported);
+            return None;
+        };
+        let end = self.total.unwrap_or(u64::MAX);
+        // A Xing or Info header in the first frame gives the length of VBR files
+        let xing = 4 + frame.side_info;
+        if let Some(tag) = data.get(xing..xing + 8).filter(|t| &t[..4] == b"Xing" || &t[..4] == b"Info") {
+            let flags = u32::from_be_bytes([tag[4], tag[5], tag[6], tag[7]]);
+            let mut fields = data[xing + 8..].chunks_exact(4).map(|f| u32::from_be_bytes([f[0], f[1], f[2], f[3]]));
+            let frames = if flags & 1 != 0 { fields.next() } else { None };
+            let bytes = if flags & 2 != 0 { fields.next() } else { None };
+            let toc_at = xing + 8 + 4 * ((flags & 1) + ((flags & 2) >> 1)) as usize;
+            let toc = if flags & 4 != 0 { data.get(toc_at..toc_at + 100).map(<[u8]>::to_vec) } else { None };
+            if let Some(frames) = frames.filter(|&f| f > 0) {
+                let duration = frames as f64 * frame.samples as f64 / frame.sample_rate as f64;
+                let data_end = bytes.map_or(end, |b| start + b as u64);
+                return Some(SeekIndex::Mp3 {
+                    data: start..data_end,
+                    bytes_per_second: (data_end.saturating_sub(start)) as f64 / duration,
+                    duration,
+                    toc,
+                });
+            }
+        }
+        let bytes_per_second = frame.bitrate as f64 / 8.0;
+        let duration = match self.total {
+            Some(total) => total.saturating_sub(start) as f64 / bytes_per_second,
+            None => f64::INFINITY,
+        };
+        Some(SeekIndex::Mp3 { data: start..end, bytes_per_second, duration, toc: None })
+    }
+
+    fn ogg_index(&mut self) -> Option<SeekIndex> {
+        if self.ogg_header.is_none() {
+            let head = self.data.contiguous(0);
+            let (_, page_len) = ogg_page(head)?;
+            let body = &head[27 + head[26] as usize..page_len];
+            self.ogg_header = if body.starts_with(b"\x01vorbis") && body.len() >= 16 {
+                Some((u32::from_le_bytes([body[12], body[13], body[14], body[15]]) as f64, 0))
+            } else if body.starts_with(b"OpusHead") && body.len() >= 12 {
+                Some((48000.0, u16::from_le_bytes([body[10], body[11]]) as i64))
+            } else {
+                self.error = Some(MediaErrorCode::SrcNotSupported);
+                return None;
+            };
+        }
+        let (granule_rate, pre_skip) = self.ogg_header?;
+        let Some(total) = self.total else {
+            // Endless stream: no duration and no seeking
+            return Some(SeekIndex::Ogg { data: 0..u64::MAX, granule_rate, pre_skip, duration: f64::INFINITY });
+        };
+        // The last page's granule position is the length of the file
+        let tail_start = total.saturating_sub(OGG_TAIL);
+        let tail = self.data.contiguous(tail_start);
+        if (tail.len() as u64) < total - tail_start {
+            return None;
+        }
+        let last = (0..tail.len().saturating_sub(3))
+            .rev()
+            .filter(|&i| &tail[i..i + 4] == b"OggS")
+            .find_map(|i| ogg_page(&tail[i..]).map(|(granule, _)| granule).filter(|&g| g >= 0))?;
+        let duration = (last - pre_skip).max(0) as f64 / granule_rate;
+        Some(SeekIndex::Ogg { data: 0..total, granule_rate, pre_skip, duration })
+    }
+
+    fn mp4_index(&mut self) -> Option<SeekIndex> {
+        // Walk the top-level boxes until `moov` has been downloaded
+        loop {
+            let header = self.data.contiguous(self.mp4_box);
+            let (size, box_type) = (header.get(..4)?, header.get(4..8)?);
+            let mut size = u32::from_be_bytes(size.try_into().ok()?) as u64;
+            if size == 1 {
+                size = u64::from_be_bytes(header.get(8..16)?.try_into().ok()?);
+            } else if size == 0 {
+                size = self.total?.saturating_sub(self.mp4_box);
+            }
+            if size < 8 {
+                self.error = Some(MediaErrorCode::SrcNotSupported);
+                return None;
+            }
+            if box_type == b"moov" {
+                let moov = header.get(..size as usize)?.to_vec();
+                let Ok(demuxer) = Mp4Demuxer::new(moov) else {
+                    self.error = Some(MediaErrorCode::SrcNotSupported);
+                    return None;
+                };
+                let Some(track) = demuxer.audio_track().map(|t| t.track_id) else {
+                    self.error = Some(MediaErrorCode::SrcNotSupported);
+                    return None;
+                };
+                let mut samples: Vec<(f64, u64, u64)> = demuxer
+                    .samples()
+                    .iter()
+                    .filter(|s| s.track_id == track)
+                    .map(|s| (s.pts.as_secs_f64(), s.offset, s.offset + s.size as u64))
+                    .collect();
+                samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let duration = demuxer.duration().map_or(0.0, |d| d.as_secs_f64());
+                return Some(SeekIndex::Mp4 { samples, duration });
+            }
+            self.mp4_box += size;
+        }
+    }
+
+    /// Bisect towards the page of an Ogg seek target with the data at hand,
+    /// then scan the pages between the bounds
+    fn advance_ogg_seek(&mut self) {
+        let Some(SeekIndex::Ogg { granule_rate, pre_skip, data: ref range, .. }) = self.index else { return };
+        let end = range.end.min(self.total.unwrap_or(u64::MAX));
+        let page_time = |granule: i64| (granule - pre_skip).max(0) as f64 / granule_rate;
+        while let Some(seek) = self.ogg_seek.as_mut() {
+            if seek.high.saturating_sub(seek.low.0) <= CHUNK_SIZE || seek.steps >= OGG_SEEK_STEPS {
+                // Playback resumes after the last page ending before the target
+                let data = self.data.contiguous(seek.low.0);
+                let (mut pos, mut best) = (0, (seek.low.0, seek.low.1));
+                let mut passed = false;
+                while let Some((granule, len)) = ogg_page(&data[pos..]) {
+                    pos += len;
+                    if granule < 0 {
+                        continue;
+                    }
+                    if page_time(granule) > seek.target {
+                        passed = true;
+                        break;
+                    }
+                    best = (seek.low.0 + pos as u64, page_time(granule));
+                }
+                if !passed && seek.low.0 + (data.len() as u64) < seek.high.min(end) {
+                    seek.guess = seek.low.0;
+                    return;
+                }
+                self.anchor = (best.1, best.0);
+                self.playback = best.0;
+                self.ogg_seek = None;
+                break;
+            }
+            let data = self.data.contiguous(seek.guess);
+            match find_ogg_page(data) {
+                Some((offset, granule, len)) => {
+                    let time = page_time(granule);
+                    if time > seek.target {
+                        seek.high = seek.guess;
+                    } else if seek.target - time > OGG_SEEK_TOLERANCE {
+                        seek.low = (seek.guess + (offset + len) as u64, time);
+                    } else {
+                        self.anchor = (time, seek.guess + (offset + len) as u64);
+                        self.playback = self.anchor.1;
+                        self.ogg_seek = None;
+                        break;
+                    }
+                }
+                // Past the last page, or a whole chunk without one
+                None if data.len() as u64 >= CHUNK_SIZE || seek.guess + data.len() as u64 >= end => {
+                    seek.high = seek.guess;
+                }
+                None => return,
+            }
+            seek.steps += 1;
+            seek.guess = seek.low.0 + (seek.high - seek.low.0) / 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fos_demux::fixtures::{init_segment, mp4_box};
+
+    /// 128 kbps, 44.1 kHz MPEG-1 Layer III frames after an ID3 tag
+    fn mp3_file(frames: usize) -> Vec<u8> {
+        let mut file = b"ID3\x04\x00\x00\x00\x00\x00\x0A".to_vec();
+        file.extend([0; 10]);
+        for _ in 0..frames {
+            let mut frame = vec![0; 417];
+            frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+            file.extend(frame);
+        }
+        file
+    }
+
+    /// Ogg Opus file with a 4 kB page every second
+    fn ogg_file(seconds: i64) -> Vec<u8> {
+        let page = |granule: i64, segments: &[u8]| {
+            let mut page = b"OggS\0\0".to_vec();
+            page.extend(granule.to_le_bytes());
+            page.extend([0; 12]);
+            page.push(segments.len() as u8);
+            page.extend(segments);
+            page.resize(page.len() + segments.iter().map(|&s| s as usize).sum::<usize>(), 0);
+            page
+        };
+        let mut file = page(0, &[19]);
+        file[28..47].copy_from_slice(b"OpusHead\x01\x01\x38\x01\x80\xBB\0\0\0\0\0");
+        for second in 1..=seconds {
+            file.extend(page(312 + second * 48000, &[255; 16]));
+        }
+        file
+    }
+
+    #[test]
+    fn test_mp3_streaming() {
+        let file = mp3_file(2000);
+        let total = Some(file.len() as u64);
+        let mut media = HTMLMediaElement::new();
+        let mut loader = ProgressiveLoader::new(&mut media, Duration::ZERO);
+        assert_eq!(loader.update(&mut media, Duration::ZERO), [LoadEvent::LoadStart]);
+
+        let range = loader.next_request().unwrap();
+        assert_eq!(range, 0..CHUNK_SIZE);
+        assert!(loader.next_request().is_none());
+        loader.on_data(0, &file[..40000], total);
+        assert_eq!(loader.format(), Some(ProgressiveFormat::Mp3));
+        // 4 kB/s so far: too slow to play through
+        let events = loader.update(&mut media, Duration::from_secs(10));
+        assert_eq!(events, [
+            LoadEvent::DurationChange, LoadEvent::LoadedMetadata, LoadEvent::Progress,
+            LoadEvent::LoadedData, LoadEvent::CanPlay,
+        ]);
+        // 2000 frames of 417 bytes at 16 kB/s
+        assert_eq!(media.duration, 52.125);
+        assert_eq!(media.ready_state, ReadyState::HaveFutureData);
+
+        // Seeking past the buffered data waits for a fetch from there
+        media.paused = false;
+        loader.seek(&mut media, 30.0);
+        assert_eq!(loader.update(&mut media, Duration::from_millis(10_100)), [LoadEvent::Waiting]);
+        let range = loader.next_request().unwrap();
+        assert_eq!(range.start, 20 + 30 * 16000);
+        loader.on_data(range.start, &file[range.start as usize..], total);
+        let events = loader.update(&mut media, Duration::from_millis(10_200));
+        assert_eq!(events, [
+            LoadEvent::CanPlay, LoadEvent::Playing, LoadEvent::CanPlayThrough, LoadEvent::Progress, LoadEvent::Suspend,
+        ]);
+        assert_eq!(media.buffered.length(), 2);
+        assert_eq!(media.buffered.start(1), Some(30.0));
+    }
+
+    #[test]
+    fn test_ogg_seek_bisection() {
+        let file = ogg_file(600);
+        let total = Some(file.len() as u64);
+        let mut media = HTMLMediaElement::new();
+        let mut loader = ProgressiveLoader::new(&mut media, Duration::ZERO);
+        let serve = |loader: &mut ProgressiveLoader| {
+            let range = loader.next_request().unwrap();
+            loader.on_data(range.start, &file[range.start as usize..range.end as usize], total);
+        };
+        serve(&mut loader);
+        // The duration comes from the last page
+        let tail = loader.next_request().unwrap();
+        assert_eq!(tail.end, file.len() as u64);
+        loader.on_data(tail.start, &file[tail.start as usize..], total);
+        loader.update(&mut media, Duration::ZERO);
+        assert_eq!(media.duration, 600.0);
+
+        loader.seek(&mut media, 450.5);
+        let mut fetches = 0;
+        while loader.ogg_seek.is_some() {
+            serve(&mut loader);
+            fetches += 1;
+        }
+        assert!(fetches < 8);
+        let (time, byte) = loader.anchor;
+        assert_eq!(time, 450.0);
+        assert_eq!(&file[byte as usize..byte as usize + 4], b"OggS");
+    }
+
+    #[test]
+    fn test_mp4_and_unsupported() {
+        let mut media = HTMLMediaElement::new();
+        let mut loader = ProgressiveLoader::new(&mut media, Duration::ZERO);
+        loader.next_request();
+        loader.on_data(0, b"not a media file at all", Some(23));
+        assert_eq!(loader.update(&mut media, Duration::ZERO), [LoadEvent::LoadStart, LoadEvent::Error]);
+        assert_eq!(media.error.as_ref().map(|e| e.code), Some(MediaErrorCode::SrcNotSupported));
+        assert!(loader.next_request().is_none());
+
+        // ftyp, then a large mdat: the loader skips to the box after it
+        let mut file = b"\0\0\0\x10ftypisom\0\0\0\0".to_vec();
+        file.extend((1_000_000u32 + 8).to_be_bytes());
+        file.extend(b"mdat");
+        let mut loader = ProgressiveLoader::new(&mut media, Duration::ZERO);
+        loader.next_request();
+        loader.on_data(0, &file, None);
+        assert_eq!(loader.next_request(), Some(1_000_024..1_000_024 + CHUNK_SIZE));
+
+        // Three 100 ms samples of 1000 bytes in a sound track
+        let table = |name: &[u8; 4], values: &[u32]| {
+            let payload: Vec<u8> = [0].iter().chain(values).flat_map(|v: &u32| v.to_be_bytes()).collect();
+            mp4_box(name, &payload)
+        };
+        let tables = [
+            table(b"stts", &[1, 3, 100]),
+            table(b"stsc", &[1, 1, 3, 1]),
+            table(b"stsz", &[1000, 3]),
+            table(b"stco", &[1, 0]),
+        ].concat();
+        let mut file = init_segment(&tables, false);
+        let hdlr = file.windows(4).position(|w| w == b"vide").unwrap();
+        file[hdlr..hdlr + 4].copy_from_slice(b"soun");
+        let stco = file.windows(4).position(|w| w == b"stco").unwrap() + 12;
+        let mdat = file.len() as u32 + 8;
+        file[stco..stco + 4].copy_from_slice(&mdat.to_be_bytes());
+        file.extend(mp4_box(b"mdat", &[0; 3000]));
+
+        let mut loader = ProgressiveLoader::new(&mut media, Duration::ZERO);
+        loader.next_request();
+        loader.on_data(0, &file[..mdat as usize + 1500], Some(file.len() as u64));
+        loader.update(&mut media, Duration::ZERO);
+        assert_eq!(media.duration, 3.0);
+        assert_eq!((media.buffered.start(0), media.buffered.end(0)), (Some(0.0), Some(0.1)));
+        loader.seek(&mut media, 0.25);
+        assert_eq!(loader.next_request(), Some(mdat as u64 + 2000..file.len() as u64));
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! TextTrack, AudioTrack, VideoTrack.
 
+use crate::vtt::{self, CueSettings, VttCue, VttError, VttRegion};
+
 /// Text track kind
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum TextTrackKind {
@@ -32,6 +34,11 @@ pub struct TextTrack {
     pub mode: TextTrackMode,
     pub cues: Vec<TextTrackCue>,
     pub active_cues: Vec<usize>,
+    /// `<track src>`
+    pub src: Option<String>,
+    pub regions: Vec<VttRegion>,
+    /// Time of the last "time marches on" run
+    last_time: f64,
 }
 
 /// Text track cue
@@ -41,9 +48,47 @@ pub struct TextTrackCue {
     pub start_time: f64,
     pub end_time: f64,
     pub pause_on_exit: bool,
+    pub settings: CueSettings,
     pub text: String,
 }
 
+impl TextTrackCue {
+    /// The cue as a WebVTT cue, for markup parsing and layout
+    pub fn to_vtt(&self) -> VttCue {
+        VttCue {
+            id: self.id.clone(),
+            start_time: self.start_time,
+            end_time: self.end_time,
+            settings: self.settings.clone(),
+            text: self.text.clone(),
+        }
+    }
+}
+
+impl From<VttCue> for TextTrackCue {
+    fn from(cue: VttCue) -> Self {
+        Self {
+            id: cue.id,
+            start_time: cue.start_time,
+            end_time: cue.end_time,
+            pause_on_exit: false,
+            settings: cue.settings,
+            text: cue.text,
+        }
+    }
+}
+
+/// Cue event from "time marches on"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextTrackEvent {
+    /// `enter` on the cue at this index
+    Enter(usize),
+    /// `exit` on the cue at this index
+    Exit(usize),
+    /// `cuechange` on the track
+    CueChange,
+}
+
 impl TextTrack {
     pub fn new(kind: TextTrackKind, label: &str, language: &str) -> Self {
         Self {
@@ -54,9 +99,21 @@ impl TextTrack {
             mode: TextTrackMode::Disabled,
             cues: Vec::new(),
             active_cues: Vec::new(),
+            src: None,
+            regions: Vec::new(),
+            last_time: 0.0,
         }
     }
     
+    /// Parse a WebVTT file into cues, returning how many were added
+    pub fn load_vtt(&mut self, input: &str) -> Result<usize, VttError> {
+        let file = vtt::parse(input)?;
+        let count = file.cues.len();
+        self.cues.extend(file.cues.into_iter().map(TextTrackCue::from));
+        self.regions = file.regions;
+        Ok(count)
+    }
+    
     pub fn add_cue(&mut self, cue: TextTrackCue) {
         self.cues.push(cue);
     }
@@ -72,6 +129,57 @@ impl TextTrack {
             .map(|(i, _)| i)
             .collect();
     }
+    
+    /// Update the active cues for a new playback position
+    ///
+    /// Cues that started and ended since the last run (and no seek happened)
+    /// still get `enter` and `exit`. Events are in time order.
+    pub fn time_marches_on(&mut self, current_time: f64, seeking: bool) -> Vec<TextTrackEvent> {
+        if self.mode == TextTrackMode::Disabled {
+            self.active_cues.clear();
+            self.last_time = current_time;
+            return Vec::new();
+        }
+        let last_time = self.last_time;
+        self.last_time = current_time;
+        let previous = std::mem::take(&mut self.active_cues);
+        self.update_active(current_time);
+
+        let missed: Vec<usize> = if !seeking && last_time <= current_time {
+            self.cues.iter().enumerate()
+                .filter(|(_, c)| c.start_time >= last_time && c.end_time <= current_time)
+                .map(|(i, _)| i)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut timed: Vec<(f64, TextTrackEvent)> = Vec::new();
+        for &i in self.active_cues.iter().filter(|i| !previous.contains(i)) {
+            timed.push((self.cues[i].start_time, TextTrackEvent::Enter(i)));
+        }
+        for &i in missed.iter().filter(|i| !previous.contains(i)) {
+            timed.push((self.cues[i].start_time, TextTrackEvent::Enter(i)));
+            timed.push((self.cues[i].end_time, TextTrackEvent::Exit(i)));
+        }
+        for &i in previous.iter().filter(|i| !self.active_cues.contains(i) && !missed.contains(i)) {
+            if let Some(cue) = self.cues.get(i) {
+                timed.push((cue.end_time.min(current_time), TextTrackEvent::Exit(i)));
+            }
+        }
+        if timed.is_empty() {
+            return Vec::new();
+        }
+        timed.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut events: Vec<TextTrackEvent> = timed.into_iter().map(|(_, e)| e).collect();
+        events.push(TextTrackEvent::CueChange);
+        events
+    }
+    
+    /// Active cues in WebVTT form, for layout
+    pub fn active_vtt_cues(&self) -> Vec<VttCue> {
+        self.active_cues.iter().filter_map(|&i| self.cues.get(i)).map(TextTrackCue::to_vtt).collect()
+    }
 }
 
 /// Audio track
@@ -131,6 +239,15 @@ impl TextTrackList {
     pub fn get_by_id(&self, id: &str) -> Option<&TextTrack> {
         self.tracks.iter().find(|t| t.id == id)
     }
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut TextTrack> { self.tracks.get_mut(index) }
+    pub fn add(&mut self, track: TextTrack) -> usize {
+        self.tracks.push(track);
+        self.tracks.len() - 1
+    }
+    /// Tracks whose cues are rendered over the video
+    pub fn showing(&self) -> impl Iterator<Item = &TextTrack> {
+        self.tracks.iter().filter(|t| t.mode == TextTrackMode::Showing)
+    }
 }
 
 #[cfg(test)]
@@ -145,10 +262,36 @@ mod tests {
             start_time: 0.0,
             end_time: 5.0,
             pause_on_exit: false,
+            settings: CueSettings::default(),
             text: "Hello".into(),
         });
         
         track.update_active(2.5);
         assert_eq!(track.active_cues.len(), 1);
     }
+    
+    #[test]
+    fn test_time_marches_on() {
+        let mut track = TextTrack::new(TextTrackKind::Subtitles, "English", "en");
+        let added = track.load_vtt("WEBVTT\n\n00:01.000 --> 00:02.000\nshort\n\n00:01.500 --> 00:05.000 line:0\nlong\n").unwrap();
+        assert_eq!(added, 2);
+        assert_eq!(track.cues[1].settings.line, crate::vtt::CueLine::Lines(0.0));
+        
+        // Disabled tracks fire nothing
+        assert!(track.time_marches_on(1.8, false).is_empty());
+        track.mode = TextTrackMode::Hidden;
+        track.time_marches_on(0.0, true);
+        
+        // The short cue was skipped over but still enters and exits
+        let events = track.time_marches_on(3.0, false);
+        assert_eq!(events, vec![
+            TextTrackEvent::Enter(0), TextTrackEvent::Enter(1), TextTrackEvent::Exit(0), TextTrackEvent::CueChange,
+        ]);
+        assert_eq!(track.active_cues, vec![1]);
+        assert!(track.time_marches_on(4.0, false).is_empty());
+        
+        // Seeking back does not replay missed cues
+        let events = track.time_marches_on(0.5, true);
+        assert_eq!(events, vec![TextTrackEvent::Exit(1), TextTrackEvent::CueChange]);
+    }
 }
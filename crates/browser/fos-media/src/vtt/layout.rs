@@ -0,0 +1,409 @@
+//! Cue Layout
+//!
+//! Positions active cues over the video following the WebVTT rendering rules:
+//! box width and offset from `position`/`size`/`align`, vertical placement from
+//! `line` with snap-to-lines overlap avoidance, and region stacking. Vertical
+//! cues are laid out like horizontal ones.
+
+use super::{CueAlign, CueLine, CueNode, CueSpanKind, LineAlign, PositionAlign, VttCue, VttRegion, WritingDirection};
+
+/// Cue font size as a fraction of the video height (5vh)
+const FONT_SIZE_RATIO: f32 = 0.05;
+const LINE_HEIGHT_RATIO: f32 = 1.2;
+/// Ruby text size relative to the cue font
+const RUBY_SCALE: f32 = 0.5;
+
+/// Measures text for line breaking
+pub trait TextMeasure {
+    /// Advance width of `text` at `font_size` pixels
+    fn measure(&mut self, text: &str, font_size: f32) -> f32;
+}
+
+/// Fixed half-em advance per character, when no font is loaded
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApproxMeasure;
+
+impl TextMeasure for ApproxMeasure {
+    fn measure(&mut self, text: &str, font_size: f32) -> f32 {
+        text.chars().count() as f32 * font_size * 0.5
+    }
+}
+
+/// Style of a run of cue text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    /// Ruby annotation, drawn above the preceding run at half size
+    pub ruby_text: bool,
+    pub voice: Option<String>,
+    pub classes: Vec<String>,
+    /// After a karaoke timestamp that is still ahead of the playback position
+    pub future: bool,
+}
+
+/// Run of text on one line
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueRun {
+    pub text: String,
+    pub style: RunStyle,
+    /// Offset from the start of the line
+    pub x: f32,
+    pub width: f32,
+}
+
+/// One line of a cue box
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueLineBox {
+    pub runs: Vec<CueRun>,
+    /// Offset from the left of the cue box
+    pub x: f32,
+    pub width: f32,
+}
+
+/// Positioned cue, in video pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueBox {
+    /// Index into the cues passed to [`layout_cues`]
+    pub cue: usize,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub lines: Vec<CueLineBox>,
+    pub font_size: f32,
+    pub line_height: f32,
+}
+
+impl CueBox {
+    fn overlaps(&self, other: &CueBox) -> bool {
+        self.x < other.x + other.width && other.x < self.x + self.width
+            && self.y < other.y + other.height && other.y < self.y + self.height
+    }
+}
+
+/// Flatten the node tree into styled text pieces
+///
+/// `future` carries the karaoke state across spans, since a timestamp inside
+/// a span still applies to the text after it.
+fn flatten(nodes: &[CueNode], style: &RunStyle, current_time: f64, future: &mut bool, out: &mut Vec<(String, RunStyle)>) {
+    for node in nodes {
+        match node {
+            CueNode::Text(text) => out.push((text.clone(), RunStyle { future: *future, ..style.clone() })),
+            CueNode::Timestamp(time) => *future = *time > current_time,
+            CueNode::Span { kind, classes, annotation, children } => {
+                let mut inner = style.clone();
+                match kind {
+                    CueSpanKind::Bold => inner.bold = true,
+                    CueSpanKind::Italic => inner.italic = true,
+                    CueSpanKind::Underline => inner.underline = true,
+                    CueSpanKind::RubyText => inner.ruby_text = true,
+                    CueSpanKind::Voice => inner.voice = annotation.clone(),
+                    CueSpanKind::Class | CueSpanKind::Ruby | CueSpanKind::Lang => {}
+                }
+                inner.classes.extend(classes.iter().cloned());
+                flatten(children, &inner, current_time, future, out);
+            }
+        }
+    }
+}
+
+fn cue_pieces(cue: &VttCue, current_time: f64) -> Vec<(String, RunStyle)> {
+    let mut pieces = Vec::new();
+    flatten(&cue.nodes(), &RunStyle::default(), current_time, &mut false, &mut pieces);
+    pieces
+}
+
+/// Break styled pieces into lines no wider than `max_width`
+fn break_lines(pieces: &[(String, RunStyle)], max_width: f32, font_size: f32, measure: &mut dyn TextMeasure) -> Vec<CueLineBox> {
+    let mut lines = vec![CueLineBox { runs: Vec::new(), x: 0.0, width: 0.0 }];
+    let mut cursor = 0.0f32;
+
+    for (text, style) in pieces {
+        if style.ruby_text {
+            // Annotate the last base run without advancing
+            let width = measure.measure(text, font_size * RUBY_SCALE);
+            let line = lines.last_mut().expect("always one line");
+            let x = line.runs.iter().rev().find(|r| !r.style.ruby_text).map_or(cursor, |r| r.x);
+            line.runs.push(CueRun { text: text.clone(), style: style.clone(), x, width });
+            continue;
+        }
+        for (i, segment) in text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(CueLineBox { runs: Vec::new(), x: 0.0, width: 0.0 });
+                cursor = 0.0;
+            }
+            for word in segment.split_inclusive(' ') {
+                let visible = measure.measure(word.trim_end(), font_size);
+                let advance = measure.measure(word, font_size);
+                let line = lines.last_mut().expect("always one line");
+                if !line.runs.is_empty() && cursor + visible > max_width {
+                    lines.push(CueLineBox { runs: Vec::new(), x: 0.0, width: 0.0 });
+                    cursor = 0.0;
+                }
+                let line = lines.last_mut().expect("always one line");
+                match line.runs.last_mut() {
+                    Some(run) if run.style == *style => {
+                        run.text.push_str(word);
+                        run.width += advance;
+                    }
+                    _ => line.runs.push(CueRun { text: word.to_string(), style: style.clone(), x: cursor, width: advance }),
+                }
+                cursor += advance;
+            }
+        }
+    }
+
+    for line in &mut lines {
+        // Trailing spaces do not count toward the line width
+        if let Some(run) = line.runs.iter_mut().rev().find(|r| !r.style.ruby_text) {
+            let trimmed = run.text.trim_end().len();
+            if trimmed < run.text.len() {
+                run.text.truncate(trimmed);
+                run.width = measure.measure(&run.text, font_size);
+            }
+        }
+        line.width = line.runs.iter().filter(|r| !r.style.ruby_text).map(|r| r.x + r.width).fold(0.0, f32::max);
+    }
+    lines.retain(|l| !l.runs.is_empty());
+    lines
+}
+
+fn align_lines(lines: &mut [CueLineBox], align: CueAlign, box_width: f32) {
+    for line in lines {
+        line.x = match align {
+            CueAlign::Left | CueAlign::Start => 0.0,
+            CueAlign::Right | CueAlign::End => box_width - line.width,
+            CueAlign::Center => (box_width - line.width) / 2.0,
+        };
+    }
+}
+
+/// Whether the region applies; cues with explicit line, size or direction ignore it
+fn cue_region<'a>(cue: &VttCue, regions: &'a [VttRegion]) -> Option<&'a VttRegion> {
+    let settings = &cue.settings;
+    if settings.line != CueLine::Auto || settings.size != 100.0 || settings.vertical != WritingDirection::Horizontal {
+        return None;
+    }
+    regions.iter().find(|r| Some(&r.id) == settings.region.as_ref())
+}
+
+/// Lay out the active cues of a track over a `width` x `height` video
+pub fn layout_cues(
+    cues: &[&VttCue],
+    regions: &[VttRegion],
+    width: f32,
+    height: f32,
+    current_time: f64,
+    measure: &mut dyn TextMeasure,
+) -> Vec<CueBox> {
+    let font_size = height * FONT_SIZE_RATIO;
+    let line_height = font_size * LINE_HEIGHT_RATIO;
+    let mut boxes: Vec<CueBox> = Vec::new();
+
+    for region in regions {
+        let members: Vec<usize> = (0..cues.len()).filter(|&i| cue_region(cues[i], regions) == Some(region)).collect();
+        boxes.extend(layout_region(region, &members, cues, width, height, font_size, current_time, measure));
+    }
+
+    for (index, cue) in cues.iter().enumerate() {
+        if cue_region(cue, regions).is_some() { continue; }
+        let settings = &cue.settings;
+        let position = settings.position.unwrap_or(match settings.align {
+            CueAlign::Left => 0.0,
+            CueAlign::Right => 100.0,
+            _ => 50.0,
+        }) as f32;
+        let position_align = match settings.position_align {
+            PositionAlign::Auto => match settings.align {
+                CueAlign::Left | CueAlign::Start => PositionAlign::LineLeft,
+                CueAlign::Right | CueAlign::End => PositionAlign::LineRight,
+                CueAlign::Center => PositionAlign::Center,
+            },
+            align => align,
+        };
+        let max_size = match position_align {
+            PositionAlign::LineLeft => 100.0 - position,
+            PositionAlign::LineRight => position,
+            _ => position.min(100.0 - position) * 2.0,
+        };
+        let size = (settings.size as f32).min(max_size);
+        let x_percent = match position_align {
+            PositionAlign::LineLeft => position,
+            PositionAlign::LineRight => position - size,
+            _ => position - size / 2.0,
+        };
+
+        let box_width = size / 100.0 * width;
+        let pieces = cue_pieces(cue, current_time);
+        let mut lines = break_lines(&pieces, box_width, font_size, measure);
+        if lines.is_empty() { continue; }
+        align_lines(&mut lines, settings.align, box_width);
+
+        let mut cue_box = CueBox {
+            cue: index,
+            x: x_percent / 100.0 * width,
+            y: 0.0,
+            width: box_width,
+            height: lines.len() as f32 * line_height,
+            lines,
+            font_size,
+            line_height,
+        };
+        if place(&mut cue_box, settings.line, settings.line_align, height, &boxes) {
+            boxes.push(cue_box);
+        }
+    }
+    boxes.sort_by_key(|b| b.cue);
+    boxes
+}
+
+/// Position a box vertically; false if it cannot be shown
+fn place(cue_box: &mut CueBox, line: CueLine, line_align: LineAlign, height: f32, placed: &[CueBox]) -> bool {
+    let fits = |b: &CueBox| b.y >= 0.0 && b.y + b.height <= height + 0.01 && !placed.iter().any(|p| p.overlaps(b));
+    match line {
+        CueLine::Percent(percent) => {
+            let anchor = percent as f32 / 100.0 * height;
+            cue_box.y = match line_align {
+                LineAlign::Start => anchor,
+                LineAlign::Center => anchor - cue_box.height / 2.0,
+                LineAlign::End => anchor - cue_box.height,
+            }
+            .clamp(0.0, (height - cue_box.height).max(0.0));
+            if fits(cue_box) { return true; }
+            // Move the smallest distance that clears the overlapping boxes
+            let mut candidates: Vec<f32> = placed.iter()
+                .flat_map(|p| [p.y - cue_box.height, p.y + p.height])
+                .collect();
+            let origin = cue_box.y;
+            candidates.sort_by(|a, b| (a - origin).abs().total_cmp(&(b - origin).abs()));
+            for y in candidates {
+                cue_box.y = y;
+                if fits(cue_box) { return true; }
+            }
+            cue_box.y = origin;
+            true
+        }
+        CueLine::Auto | CueLine::Lines(_) => {
+            let line = match line { CueLine::Lines(n) => n.trunc() as f32, _ => -1.0 };
+            let step = cue_box.line_height;
+            let start = if line < 0.0 {
+                // Bottom line of the box sits on the given line from the bottom
+                height + line * step - (cue_box.height - step)
+            } else {
+                line * step
+            };
+            let mut direction = if line < 0.0 { -step } else { step };
+            cue_box.y = start;
+            let mut switched = false;
+            loop {
+                if fits(cue_box) { return true; }
+                cue_box.y += direction;
+                let outside = cue_box.y < 0.0 || cue_box.y + cue_box.height > height + 0.01;
+                if outside {
+                    if switched { return false; }
+                    switched = true;
+                    direction = -direction;
+                    cue_box.y = start;
+                }
+            }
+        }
+    }
+}
+
+/// Stack region cues from the bottom of the region, clipping lines above it
+#[allow(clippy::too_many_arguments)]
+fn layout_region(
+    region: &VttRegion,
+    members: &[usize],
+    cues: &[&VttCue],
+    width: f32,
+    height: f32,
+    font_size: f32,
+    current_time: f64,
+    measure: &mut dyn TextMeasure,
+) -> Vec<CueBox> {
+    let line_height = font_size * LINE_HEIGHT_RATIO;
+    let region_width = region.width as f32 / 100.0 * width;
+    let region_height = region.lines as f32 * line_height;
+    let left = region.viewport_anchor.0 as f32 / 100.0 * width - region.region_anchor.0 as f32 / 100.0 * region_width;
+    let top = region.viewport_anchor.1 as f32 / 100.0 * height - region.region_anchor.1 as f32 / 100.0 * region_height;
+
+    let mut boxes = Vec::new();
+    let mut bottom = top + region_height;
+    for &index in members.iter().rev() {
+        let cue = cues[index];
+        let pieces = cue_pieces(cue, current_time);
+        let mut lines = break_lines(&pieces, region_width, font_size, measure);
+        align_lines(&mut lines, cue.settings.align, region_width);
+        // Drop lines that scrolled out of the top of the region
+        let room = ((bottom - top) / line_height).round().max(0.0) as usize;
+        if room == 0 { break; }
+        if lines.len() > room {
+            lines.drain(..lines.len() - room);
+        }
+        if lines.is_empty() { continue; }
+        let box_height = lines.len() as f32 * line_height;
+        bottom -= box_height;
+        boxes.push(CueBox { cue: index, x: left, y: bottom, width: region_width, height: box_height, lines, font_size, line_height });
+    }
+    boxes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vtt::{parse, CueSettings};
+
+    fn cue(text: &str, settings: &str) -> VttCue {
+        VttCue { id: String::new(), start_time: 0.0, end_time: 10.0, settings: CueSettings::parse(settings, &[]), text: text.into() }
+    }
+
+    #[test]
+    fn test_default_cue_sits_at_bottom_center() {
+        // 1000x400 video: 20px font, 24px lines, 10px per character
+        let c = cue("Hello world", "");
+        let boxes = layout_cues(&[&c], &[], 1000.0, 400.0, 0.0, &mut ApproxMeasure);
+        let b = &boxes[0];
+        assert_eq!((b.x, b.width, b.y, b.height), (0.0, 1000.0, 376.0, 24.0));
+        assert_eq!((b.lines[0].width, b.lines[0].x), (110.0, 445.0));
+    }
+
+    #[test]
+    fn test_position_size_align_and_wrap() {
+        let c = cue("aaaa bbbb cccc", "position:10%,line-left size:10% align:start line:0");
+        let boxes = layout_cues(&[&c], &[], 1000.0, 400.0, 0.0, &mut ApproxMeasure);
+        let b = &boxes[0];
+        assert_eq!((b.x, b.width, b.y), (100.0, 100.0, 0.0));
+        let text: Vec<String> = b.lines.iter().map(|l| l.runs.iter().map(|r| r.text.as_str()).collect()).collect();
+        assert_eq!(text, vec!["aaaa bbbb", "cccc"]);
+        assert!(b.lines.iter().all(|l| l.x == 0.0));
+
+        let pct = cue("x", "line:50%,center");
+        let b = &layout_cues(&[&pct], &[], 1000.0, 400.0, 0.0, &mut ApproxMeasure)[0];
+        assert_eq!(b.y, 188.0);
+    }
+
+    #[test]
+    fn test_overlap_avoidance_and_styles() {
+        let first = cue("<b>one</b>", "");
+        let second = cue("<00:00:05.000>two", "");
+        let boxes = layout_cues(&[&first, &second], &[], 1000.0, 400.0, 1.0, &mut ApproxMeasure);
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(boxes[0].y, 376.0);
+        assert_eq!(boxes[1].y, 352.0);
+        assert!(boxes[0].lines[0].runs[0].style.bold);
+        assert!(boxes[1].lines[0].runs[0].style.future);
+    }
+
+    #[test]
+    fn test_region_scrolls_lines_out() {
+        let file = parse("WEBVTT\n\nREGION\nid:r width:50% lines:2 regionanchor:0%,100% viewportanchor:10%,90%\n\n\
+00:00.000 --> 00:05.000 region:r\nfirst\n\n00:00.000 --> 00:05.000 region:r\nsecond\nthird\n").unwrap();
+        let cues: Vec<&VttCue> = file.cues.iter().collect();
+        let boxes = layout_cues(&cues, &file.regions, 1000.0, 400.0, 0.0, &mut ApproxMeasure);
+        assert_eq!(boxes.len(), 1);
+        assert_eq!((boxes[0].cue, boxes[0].x, boxes[0].y, boxes[0].lines.len()), (1, 100.0, 312.0, 2));
+    }
+}
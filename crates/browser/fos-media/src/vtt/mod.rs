@@ -0,0 +1,595 @@
+//! WebVTT
+//!
+//! Parser for WebVTT files: cue timings and settings, regions, style blocks
+//! and the cue text markup (classes, italics, voices, ruby, timestamps).
+//! [`layout`] turns active cues into positioned boxes over the video.
+
+pub mod layout;
+
+use thiserror::Error;
+
+/// WebVTT parse error
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum VttError {
+    #[error("Missing WEBVTT signature")]
+    MissingSignature,
+}
+
+/// Writing direction (`vertical:`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WritingDirection {
+    #[default]
+    Horizontal,
+    VerticalRl,
+    VerticalLr,
+}
+
+/// Cue line position (`line:`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CueLine {
+    #[default]
+    Auto,
+    /// Line number; negative counts from the bottom (snap-to-lines)
+    Lines(f64),
+    /// Percentage of the video height
+    Percent(f64),
+}
+
+/// Which part of the cue box `line` positions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineAlign {
+    #[default]
+    Start,
+    Center,
+    End,
+}
+
+/// Which part of the cue box `position` positions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionAlign {
+    LineLeft,
+    Center,
+    LineRight,
+    #[default]
+    Auto,
+}
+
+/// Text alignment within the cue box (`align:`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CueAlign {
+    Start,
+    #[default]
+    Center,
+    End,
+    Left,
+    Right,
+}
+
+/// Cue settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueSettings {
+    pub vertical: WritingDirection,
+    pub line: CueLine,
+    pub line_align: LineAlign,
+    /// Percentage of the video width; `None` is auto
+    pub position: Option<f64>,
+    pub position_align: PositionAlign,
+    /// Percentage of the video width
+    pub size: f64,
+    pub align: CueAlign,
+    pub region: Option<String>,
+}
+
+impl Default for CueSettings {
+    fn default() -> Self {
+        Self {
+            vertical: WritingDirection::Horizontal,
+            line: CueLine::Auto,
+            line_align: LineAlign::Start,
+            position: None,
+            position_align: PositionAlign::Auto,
+            size: 100.0,
+            align: CueAlign::Center,
+            region: None,
+        }
+    }
+}
+
+impl CueSettings {
+    /// Parse a cue settings list (`line:-2 position:10%,line-left align:start`)
+    pub fn parse(input: &str, regions: &[VttRegion]) -> Self {
+        let mut settings = Self::default();
+        for setting in input.split_whitespace() {
+            let Some((name, value)) = setting.split_once(':') else { continue };
+            if value.is_empty() { continue; }
+            match name {
+                "vertical" => match value {
+                    "rl" => settings.vertical = WritingDirection::VerticalRl,
+                    "lr" => settings.vertical = WritingDirection::VerticalLr,
+                    _ => {}
+                },
+                "line" => {
+                    let (number, align) = match value.split_once(',') {
+                        Some((n, a)) => (n, Some(a)),
+                        None => (value, None),
+                    };
+                    let line = if let Some(pct) = number.strip_suffix('%') {
+                        parse_percentage(pct).map(CueLine::Percent)
+                    } else if !number.is_empty() && number.trim_start_matches('-').chars().all(|c| c.is_ascii_digit() || c == '.') {
+                        number.parse().ok().map(CueLine::Lines)
+                    } else {
+                        None
+                    };
+                    let line_align = match align {
+                        None => Some(LineAlign::Start),
+                        Some("start") => Some(LineAlign::Start),
+                        Some("center") => Some(LineAlign::Center),
+                        Some("end") => Some(LineAlign::End),
+                        Some(_) => None,
+                    };
+                    if let (Some(line), Some(line_align)) = (line, line_align) {
+                        settings.line = line;
+                        settings.line_align = line_align;
+                    }
+                }
+                "position" => {
+                    let (number, align) = match value.split_once(',') {
+                        Some((n, a)) => (n, Some(a)),
+                        None => (value, None),
+                    };
+                    let align = match align {
+                        None => Some(PositionAlign::Auto),
+                        Some("line-left") => Some(PositionAlign::LineLeft),
+                        Some("center") => Some(PositionAlign::Center),
+                        Some("line-right") => Some(PositionAlign::LineRight),
+                        Some(_) => None,
+                    };
+                    if let (Some(position), Some(align)) = (number.strip_suffix('%').and_then(parse_percentage), align) {
+                        settings.position = Some(position);
+                        settings.position_align = align;
+                    }
+                }
+                "size" => {
+                    if let Some(size) = value.strip_suffix('%').and_then(parse_percentage) {
+                        settings.size = size;
+                    }
+                }
+                "align" => match value {
+                    "start" => settings.align = CueAlign::Start,
+                    "center" => settings.align = CueAlign::Center,
+                    "end" => settings.align = CueAlign::End,
+                    "left" => settings.align = CueAlign::Left,
+                    "right" => settings.align = CueAlign::Right,
+                    _ => {}
+                },
+                "region" if regions.iter().any(|r| r.id == value) => {
+                    settings.region = Some(value.to_string());
+                }
+                _ => {}
+            }
+        }
+        settings
+    }
+}
+
+/// Region (`REGION` block)
+#[derive(Debug, Clone, PartialEq)]
+pub struct VttRegion {
+    pub id: String,
+    /// Percentage of the video width
+    pub width: f64,
+    pub lines: u32,
+    /// Point of the region (percent x, y) placed at `viewport_anchor`
+    pub region_anchor: (f64, f64),
+    pub viewport_anchor: (f64, f64),
+    /// Cues scroll up into the region instead of replacing each other
+    pub scroll_up: bool,
+}
+
+impl Default for VttRegion {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            width: 100.0,
+            lines: 3,
+            region_anchor: (0.0, 100.0),
+            viewport_anchor: (0.0, 100.0),
+            scroll_up: false,
+        }
+    }
+}
+
+impl VttRegion {
+    fn parse(settings: &str) -> Option<Self> {
+        let mut region = Self::default();
+        for setting in settings.split_whitespace() {
+            let Some((name, value)) = setting.split_once(':') else { continue };
+            match name {
+                "id" if !value.contains("-->") => region.id = value.to_string(),
+                "width" => {
+                    if let Some(width) = value.strip_suffix('%').and_then(parse_percentage) {
+                        region.width = width;
+                    }
+                }
+                "lines" => {
+                    if let Ok(lines) = value.parse() {
+                        region.lines = lines;
+                    }
+                }
+                "regionanchor" => {
+                    if let Some(anchor) = parse_anchor(value) {
+                        region.region_anchor = anchor;
+                    }
+                }
+                "viewportanchor" => {
+                    if let Some(anchor) = parse_anchor(value) {
+                        region.viewport_anchor = anchor;
+                    }
+                }
+                "scroll" if value == "up" => region.scroll_up = true,
+                _ => {}
+            }
+        }
+        (!region.id.is_empty()).then_some(region)
+    }
+}
+
+fn parse_percentage(s: &str) -> Option<f64> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit() || c == '.') { return None; }
+    s.parse::<f64>().ok().filter(|p| (0.0..=100.0).contains(p))
+}
+
+fn parse_anchor(s: &str) -> Option<(f64, f64)> {
+    let (x, y) = s.split_once(',')?;
+    Some((parse_percentage(x.strip_suffix('%')?)?, parse_percentage(y.strip_suffix('%')?)?))
+}
+
+/// Parse a WebVTT timestamp (`mm:ss.ttt` or `hh:mm:ss.ttt`) into seconds
+pub fn parse_timestamp(s: &str) -> Option<f64> {
+    let (clock, millis) = s.split_once('.')?;
+    if millis.len() != 3 || !millis.chars().all(|c| c.is_ascii_digit()) { return None; }
+    let parts: Vec<&str> = clock.split(':').collect();
+    let (hours, minutes, seconds) = match parts[..] {
+        [m, s] => ("0", m, s),
+        [h, m, s] if h.len() >= 2 => (h, m, s),
+        _ => return None,
+    };
+    let digits = |p: &str| p.chars().all(|c| c.is_ascii_digit());
+    if !digits(hours) || minutes.len() != 2 || seconds.len() != 2 || !digits(minutes) || !digits(seconds) {
+        return None;
+    }
+    let (h, m, sec): (f64, f64, f64) = (hours.parse().ok()?, minutes.parse().ok()?, seconds.parse().ok()?);
+    if m > 59.0 || sec > 59.0 { return None; }
+    Some(h * 3600.0 + m * 60.0 + sec + millis.parse::<f64>().ok()? / 1000.0)
+}
+
+/// Cue
+#[derive(Debug, Clone, PartialEq)]
+pub struct VttCue {
+    pub id: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub settings: CueSettings,
+    /// Cue text with markup
+    pub text: String,
+}
+
+impl VttCue {
+    /// Cue text as a node tree
+    pub fn nodes(&self) -> Vec<CueNode> {
+        parse_cue_text(&self.text)
+    }
+}
+
+/// Parsed WebVTT file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VttFile {
+    pub cues: Vec<VttCue>,
+    pub regions: Vec<VttRegion>,
+    /// Contents of `STYLE` blocks
+    pub styles: Vec<String>,
+}
+
+/// Parse a WebVTT file
+pub fn parse(input: &str) -> Result<VttFile, VttError> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input).replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines = input.split('\n').peekable();
+
+    let signature = lines.next().unwrap_or("");
+    let rest = signature.strip_prefix("WEBVTT").ok_or(VttError::MissingSignature)?;
+    if !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return Err(VttError::MissingSignature);
+    }
+    // Header lines run to the first blank line
+    for line in lines.by_ref() {
+        if line.is_empty() { break; }
+    }
+
+    let mut file = VttFile::default();
+    let mut seen_cue = false;
+    while lines.peek().is_some() {
+        let mut block: Vec<&str> = Vec::new();
+        while let Some(&line) = lines.peek() {
+            lines.next();
+            if line.is_empty() {
+                if block.is_empty() { continue; }
+                break;
+            }
+            block.push(line);
+        }
+        if block.is_empty() { continue; }
+
+        let arrow = block.iter().take(2).position(|l| l.contains("-->"));
+        match arrow {
+            Some(timing) => {
+                seen_cue = true;
+                let id = if timing == 1 { block[0].to_string() } else { String::new() };
+                // A later line with an arrow starts a cue without a blank line
+                let text_end = block.iter().skip(timing + 1).position(|l| l.contains("-->")).map(|i| i + timing + 1);
+                let text_lines = &block[timing + 1..text_end.unwrap_or(block.len())];
+                if let Some(cue) = parse_cue(id, block[timing], text_lines, &file.regions) {
+                    file.cues.push(cue);
+                }
+                if let Some(end) = text_end {
+                    let text = &block[end + 1..];
+                    if let Some(cue) = parse_cue(String::new(), block[end], text, &file.regions) {
+                        file.cues.push(cue);
+                    }
+                }
+            }
+            None if seen_cue => {}
+            None => {
+                let first = block[0];
+                if first == "STYLE" || first.starts_with("STYLE ") || first.starts_with("STYLE\t") {
+                    file.styles.push(block[1..].join("\n"));
+                } else if first == "REGION" || first.starts_with("REGION ") || first.starts_with("REGION\t") {
+                    if let Some(region) = VttRegion::parse(&block[1..].join(" ")) {
+                        file.regions.retain(|r| r.id != region.id);
+                        file.regions.push(region);
+                    }
+                }
+                // NOTE blocks and anything else are skipped
+            }
+        }
+    }
+    Ok(file)
+}
+
+fn parse_cue(id: String, timing: &str, text: &[&str], regions: &[VttRegion]) -> Option<VttCue> {
+    let (start, rest) = timing.split_once("-->")?;
+    let rest = rest.trim_start();
+    let (end, settings) = rest.split_once([' ', '\t']).unwrap_or((rest, ""));
+    Some(VttCue {
+        id,
+        start_time: parse_timestamp(start.trim())?,
+        end_time: parse_timestamp(end)?,
+        settings: CueSettings::parse(settings, regions),
+        text: text.join("\n"),
+    })
+}
+
+/// Cue text markup element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CueSpanKind {
+    Class,
+    Italic,
+    Bold,
+    Underline,
+    Ruby,
+    RubyText,
+    Voice,
+    Lang,
+}
+
+impl CueSpanKind {
+    fn from_tag(tag: &str) -> Option<Self> {
+        Some(match tag {
+            "c" => Self::Class,
+            "i" => Self::Italic,
+            "b" => Self::Bold,
+            "u" => Self::Underline,
+            "ruby" => Self::Ruby,
+            "rt" => Self::RubyText,
+            "v" => Self::Voice,
+            "lang" => Self::Lang,
+            _ => return None,
+        })
+    }
+}
+
+/// Node of parsed cue text
+#[derive(Debug, Clone, PartialEq)]
+pub enum CueNode {
+    Text(String),
+    /// Karaoke timestamp (seconds)
+    Timestamp(f64),
+    Span {
+        kind: CueSpanKind,
+        classes: Vec<String>,
+        /// Voice name or language tag
+        annotation: Option<String>,
+        children: Vec<CueNode>,
+    },
+}
+
+impl CueNode {
+    /// Text content without markup
+    pub fn plain_text(nodes: &[CueNode]) -> String {
+        let mut out = String::new();
+        for node in nodes {
+            match node {
+                CueNode::Text(text) => out.push_str(text),
+                CueNode::Timestamp(_) => {}
+                CueNode::Span { kind: CueSpanKind::RubyText, .. } => {}
+                CueNode::Span { children, .. } => out.push_str(&Self::plain_text(children)),
+            }
+        }
+        out
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let entity = [("&amp;", "&"), ("&lt;", "<"), ("&gt;", ">"), ("&lrm;", "\u{200e}"), ("&rlm;", "\u{200f}"), ("&nbsp;", "\u{a0}")]
+            .iter()
+            .find(|(name, _)| rest.starts_with(name));
+        match entity {
+            Some((name, value)) => {
+                out.push_str(value);
+                rest = &rest[name.len()..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Span still waiting for its end tag
+struct OpenSpan {
+    kind: CueSpanKind,
+    tag: String,
+    classes: Vec<String>,
+    annotation: Option<String>,
+    children: Vec<CueNode>,
+}
+
+/// Builds the cue node tree from tokens
+#[derive(Default)]
+struct CueTreeBuilder {
+    stack: Vec<OpenSpan>,
+    root: Vec<CueNode>,
+}
+
+impl CueTreeBuilder {
+    fn push(&mut self, node: CueNode) {
+        match self.stack.last_mut() {
+            Some(open) => open.children.push(node),
+            None => self.root.push(node),
+        }
+    }
+
+    fn close(&mut self) {
+        if let Some(span) = self.stack.pop() {
+            self.push(CueNode::Span { kind: span.kind, classes: span.classes, annotation: span.annotation, children: span.children });
+        }
+    }
+
+    fn finish(mut self) -> Vec<CueNode> {
+        while !self.stack.is_empty() {
+            self.close();
+        }
+        self.root
+    }
+}
+
+/// Parse cue text markup into a node tree
+pub fn parse_cue_text(text: &str) -> Vec<CueNode> {
+    let mut tree = CueTreeBuilder::default();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let Some(open) = rest.find('<') else {
+            tree.push(CueNode::Text(decode_entities(rest)));
+            break;
+        };
+        if open > 0 {
+            tree.push(CueNode::Text(decode_entities(&rest[..open])));
+        }
+        let tag_end = rest[open..].find('>').map(|i| open + i);
+        let tag = &rest[open + 1..tag_end.unwrap_or(rest.len())];
+        rest = tag_end.map_or("", |end| &rest[end + 1..]);
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            if let Some(depth) = tree.stack.iter().rposition(|s| s.tag == name) {
+                while tree.stack.len() > depth {
+                    tree.close();
+                }
+            }
+        } else if let Some(time) = parse_timestamp(tag.trim()) {
+            tree.push(CueNode::Timestamp(time));
+        } else {
+            let (head, annotation) = match tag.split_once([' ', '\t', '\n']) {
+                Some((head, annotation)) => (head, Some(annotation.trim().to_string()).filter(|a| !a.is_empty())),
+                None => (tag, None),
+            };
+            let mut parts = head.split('.');
+            let name = parts.next().unwrap_or("");
+            let classes = parts.filter(|c| !c.is_empty()).map(str::to_string).collect();
+            let Some(kind) = CueSpanKind::from_tag(name) else { continue };
+            // <rt> is only valid inside <ruby>; a new <rt> closes the previous one
+            if kind == CueSpanKind::RubyText {
+                if !tree.stack.iter().any(|s| s.kind == CueSpanKind::Ruby) { continue; }
+                if tree.stack.last().is_some_and(|s| s.kind == CueSpanKind::RubyText) {
+                    tree.close();
+                }
+            }
+            let annotation = match kind {
+                CueSpanKind::Voice | CueSpanKind::Lang => annotation,
+                _ => None,
+            };
+            tree.stack.push(OpenSpan { kind, tag: name.to_string(), classes, annotation, children: Vec::new() });
+        }
+    }
+    tree.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "WEBVTT - sample\r\nKind: captions\r\n\r\n\
+REGION\nid:fred width:40% lines:3\nregionanchor:0%,100% viewportanchor:10%,90% scroll:up\n\n\
+STYLE\n::cue { color: yellow }\n\n\
+NOTE this is ignored\n\n\
+intro\n00:00.500 --> 00:02.000 line:-2 position:10%,line-left size:35% align:start region:fred\n<v Roger>Hello</v> &amp; welcome\n\n\
+01:00:00.000 --> 01:00:01.250 vertical:rl line:50%,center bogus:1\nOne\nTwo\n00:00:03.000 --> 00:00:04.000\nChained\n";
+
+    #[test]
+    fn test_parse_file() {
+        let file = parse(SAMPLE).unwrap();
+        assert_eq!(file.regions.len(), 1);
+        assert_eq!(file.regions[0].viewport_anchor, (10.0, 90.0));
+        assert!(file.regions[0].scroll_up);
+        assert_eq!(file.styles, vec!["::cue { color: yellow }".to_string()]);
+        assert_eq!(file.cues.len(), 3);
+
+        let intro = &file.cues[0];
+        assert_eq!((intro.id.as_str(), intro.start_time, intro.end_time), ("intro", 0.5, 2.0));
+        assert_eq!(intro.settings.line, CueLine::Lines(-2.0));
+        assert_eq!((intro.settings.position, intro.settings.position_align), (Some(10.0), PositionAlign::LineLeft));
+        assert_eq!((intro.settings.size, intro.settings.align), (35.0, CueAlign::Start));
+        assert_eq!(intro.settings.region.as_deref(), Some("fred"));
+
+        let second = &file.cues[1];
+        assert_eq!(second.start_time, 3600.0);
+        assert_eq!(second.settings.vertical, WritingDirection::VerticalRl);
+        assert_eq!((second.settings.line, second.settings.line_align), (CueLine::Percent(50.0), LineAlign::Center));
+        assert_eq!(second.text, "One\nTwo");
+        assert_eq!(file.cues[2].text, "Chained");
+
+        assert_eq!(parse("WEBVTTX\n"), Err(VttError::MissingSignature));
+        assert_eq!(parse_timestamp("1:00.000"), None);
+        assert_eq!(parse_timestamp("00:60.000"), None);
+    }
+
+    #[test]
+    fn test_cue_text_markup() {
+        let nodes = parse_cue_text("<v.loud Esme>It's <i>a</i> <c.red.bold>test</c> &lt;3<00:01.500><ruby>漢<rt>kan</rt>字<rt>ji</ruby>");
+        let CueNode::Span { kind, classes, annotation, children } = &nodes[0] else { panic!() };
+        assert_eq!((*kind, classes.clone(), annotation.as_deref()), (CueSpanKind::Voice, vec!["loud".to_string()], Some("Esme")));
+        assert!(children.contains(&CueNode::Timestamp(1.5)));
+        assert!(matches!(&children[1], CueNode::Span { kind: CueSpanKind::Italic, .. }));
+        let ruby = children.iter().find(|n| matches!(n, CueNode::Span { kind: CueSpanKind::Ruby, .. })).unwrap();
+        let CueNode::Span { children: ruby, .. } = ruby else { panic!() };
+        assert_eq!(ruby.iter().filter(|n| matches!(n, CueNode::Span { kind: CueSpanKind::RubyText, .. })).count(), 2);
+        assert_eq!(CueNode::plain_text(&nodes), "It's a test <3漢字");
+    }
+}
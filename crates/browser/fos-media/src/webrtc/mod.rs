@@ -5,6 +5,7 @@
 pub mod connection;
 pub mod datachannel;
 pub mod screen;
+pub mod user_media;
 pub mod ice;
 pub mod stun;
 pub mod turn;
@@ -19,7 +20,14 @@ pub use connection::{
     MediaStream, MediaStreamTrack, MediaStreamTrackKind, MediaStreamTrackState,
 };
 pub use datachannel::{RTCDataChannel, RTCDataChannelState, RTCDataChannelInit};
-pub use screen::{ScreenCapture, DisplayMediaStreamOptions};
+pub use screen::{
+    ScreenCapture, ScreenCaptureError, DisplayMediaRequest, DisplayMediaStreamOptions,
+    DisplayMediaVideoOptions, DisplaySurface, CursorCaptureConstraint,
+};
+pub use user_media::{
+    UserMedia, UserMediaError, MediaStreamConstraints, MediaTrackConstraints, MediaTrackSettings,
+    ConstrainRange, ConstrainValue,
+};
 pub use ice::{IceAgent, IceCandidate, IceState};
 pub use stun::StunMessage;
 pub use turn::TurnClient;
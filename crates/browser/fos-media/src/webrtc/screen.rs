@@ -1,10 +1,20 @@
 //! Screen Sharing
 //!
-//! Screen capture for WebRTC.
+//! Screen capture for WebRTC. `getDisplayMedia` is a two-step flow: the
+//! browser asks for a [`DisplayMediaRequest`] listing the capturable
+//! sources, shows them in its picker, then starts the chosen one. Frames
+//! of each capture are read per video track.
+
+use super::connection::{MediaStream, MediaStreamTrack, MediaStreamTrackKind, MediaStreamTrackState};
+use crate::capture::{
+    create_capture_backend, CaptureConfig, CaptureSource, CapturedFrame, ScreenCaptureBackend,
+    TabCapture, TabFrameSink,
+};
 
 /// Display media options
 #[derive(Debug, Clone, Default)]
 pub struct DisplayMediaStreamOptions {
+    /// Video constraints; `None` requests video with default settings
     pub video: Option<DisplayMediaVideoOptions>,
     pub audio: Option<bool>,
 }
@@ -16,6 +26,9 @@ pub struct DisplayMediaVideoOptions {
     pub display_surface: Option<DisplaySurface>,
     pub logical_surface: Option<bool>,
     pub suppress_local_audio_playback: Option<bool>,
+    pub frame_rate: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
 }
 
 /// Cursor capture constraint
@@ -35,50 +48,183 @@ pub enum DisplaySurface {
     Window,
 }
 
+/// Sources offered for one `getDisplayMedia` call
+#[derive(Debug, Clone)]
+pub struct DisplayMediaRequest {
+    pub options: DisplayMediaStreamOptions,
+    /// Picker entries, the preferred display surface first
+    pub sources: Vec<CaptureSource>,
+}
+
+/// A running capture
+struct CaptureSession {
+    stream_id: String,
+    video_track: String,
+    audio_track: Option<String>,
+    source: CaptureSource,
+    backend: Box<dyn ScreenCaptureBackend>,
+    /// Frames pushed by the browser for tab sources
+    tab_sink: Option<TabFrameSink>,
+}
+
 /// Screen capture manager
-#[derive(Debug, Default)]
 pub struct ScreenCapture {
     pub capturing: bool,
     pub stream_id: Option<String>,
+    sessions: Vec<CaptureSession>,
+    backend_factory: fn() -> Box<dyn ScreenCaptureBackend>,
+}
+
+impl std::fmt::Debug for ScreenCapture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScreenCapture")
+            .field("capturing", &self.capturing)
+            .field("sources", &self.sessions.iter().map(|s| &s.source.id).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Default for ScreenCapture {
+    fn default() -> Self { Self::new() }
 }
 
 impl ScreenCapture {
-    pub fn new() -> Self { Self::default() }
-    
-    /// Request screen capture
-    pub fn get_display_media(&mut self, options: DisplayMediaStreamOptions) -> Result<super::connection::MediaStream, ScreenCaptureError> {
-        // Would prompt user and start capture
+    pub fn new() -> Self { Self::with_backend_factory(create_capture_backend) }
+
+    /// Use a specific backend for monitor and window captures
+    pub fn with_backend_factory(backend_factory: fn() -> Box<dyn ScreenCaptureBackend>) -> Self {
+        Self { capturing: false, stream_id: None, sessions: Vec::new(), backend_factory }
+    }
+
+    /// Collect the sources to offer in the picker. `tabs` are the browser's
+    /// own tabs (see [`CaptureSource::tab`]).
+    pub fn request(&self, options: DisplayMediaStreamOptions, tabs: Vec<CaptureSource>) -> Result<DisplayMediaRequest, ScreenCaptureError> {
+        let mut sources = (self.backend_factory)().enumerate_sources();
+        sources.extend(tabs);
+        if sources.is_empty() {
+            return Err(ScreenCaptureError::NotFound);
+        }
+        // displaySurface is a hint: the preferred kind is listed first
+        if let Some(preferred) = options.video.as_ref().and_then(|v| v.display_surface) {
+            sources.sort_by_key(|s| s.surface != preferred);
+        }
+        Ok(DisplayMediaRequest { options, sources })
+    }
+
+    /// Start capturing the source picked by the user; `None` means the
+    /// picker was dismissed
+    pub fn start(&mut self, request: &DisplayMediaRequest, source_id: Option<&str>) -> Result<MediaStream, ScreenCaptureError> {
+        let source_id = source_id.ok_or(ScreenCaptureError::NotAllowed)?;
+        let source = request.sources.iter().find(|s| s.id == source_id).ok_or(ScreenCaptureError::AbortError)?.clone();
+
+        let video = request.options.video.clone().unwrap_or_default();
+        let config = CaptureConfig {
+            frame_rate: video.frame_rate.unwrap_or(30.0),
+            max_width: video.width.unwrap_or(0),
+            max_height: video.height.unwrap_or(0),
+            cursor: video.cursor,
+        };
+        let (mut backend, tab_sink): (Box<dyn ScreenCaptureBackend>, _) = match source.tab_id {
+            Some(_) => {
+                let sink = TabFrameSink::new();
+                (Box::new(TabCapture::new(sink.clone())), Some(sink))
+            }
+            None => ((self.backend_factory)(), None),
+        };
+        backend.start(&source, config).map_err(|_| ScreenCaptureError::NotReadable)?;
+
+        let mut stream = MediaStream::new();
+        let video_track = format!("{}-video", uuid_v4());
+        stream.add_track(Self::track(&video_track, MediaStreamTrackKind::Video, &source.name));
+        let audio_track = (request.options.audio.unwrap_or(false) && source.has_audio).then(|| {
+            let id = format!("{}-audio", uuid_v4());
+            stream.add_track(Self::track(&id, MediaStreamTrackKind::Audio, &format!("{} audio", source.name)));
+            id
+        });
+
+        self.sessions.push(CaptureSession { stream_id: stream.id.clone(), video_track, audio_track, source, backend, tab_sink });
         self.capturing = true;
-        self.stream_id = Some(uuid_v4());
-        
-        let mut stream = super::connection::MediaStream::new();
-        stream.add_track(super::connection::MediaStreamTrack {
-            id: uuid_v4(),
-            kind: super::connection::MediaStreamTrackKind::Video,
-            label: "Screen".into(),
+        self.stream_id = Some(stream.id.clone());
+        Ok(stream)
+    }
+
+    /// Request screen capture, accepting the first offered source without a
+    /// picker (automation and headless use)
+    pub fn get_display_media(&mut self, options: DisplayMediaStreamOptions) -> Result<MediaStream, ScreenCaptureError> {
+        let request = self.request(options, Vec::new())?;
+        let first = request.sources[0].id.clone();
+        self.start(&request, Some(&first))
+    }
+
+    fn track(id: &str, kind: MediaStreamTrackKind, label: &str) -> MediaStreamTrack {
+        MediaStreamTrack {
+            id: id.to_string(),
+            kind,
+            label: label.to_string(),
             enabled: true,
             muted: false,
-            ready_state: super::connection::MediaStreamTrackState::Live,
-        });
-        
-        if options.audio.unwrap_or(false) {
-            stream.add_track(super::connection::MediaStreamTrack {
-                id: uuid_v4(),
-                kind: super::connection::MediaStreamTrackKind::Audio,
-                label: "System Audio".into(),
-                enabled: true,
-                muted: false,
-                ready_state: super::connection::MediaStreamTrackState::Live,
-            });
+            ready_state: MediaStreamTrackState::Live,
         }
-        
-        Ok(stream)
     }
-    
+
+    /// Next frame for a capture's video track
+    pub fn read_frame(&mut self, track_id: &str) -> Option<CapturedFrame> {
+        self.sessions.iter_mut().find(|s| s.video_track == track_id)?.backend.next_frame()
+    }
+
+    /// State of a capture track; tracks of stopped captures have ended
+    pub fn track_state(&self, track_id: &str) -> MediaStreamTrackState {
+        let live = self.sessions.iter().any(|s| s.video_track == track_id || s.audio_track.as_deref() == Some(track_id));
+        if live { MediaStreamTrackState::Live } else { MediaStreamTrackState::Ended }
+    }
+
+    /// Whether a capture of the stream is still running
+    pub fn is_stream_live(&self, stream_id: &str) -> bool {
+        self.sessions.iter().any(|s| s.stream_id == stream_id)
+    }
+
+    /// Sources currently being captured (for capture indicators)
+    pub fn active_sources(&self) -> Vec<&CaptureSource> {
+        self.sessions.iter().map(|s| &s.source).collect()
+    }
+
+    /// Frame sinks of captured tabs, which the browser feeds after compositing
+    pub fn captured_tabs(&self) -> Vec<(u64, TabFrameSink)> {
+        self.sessions.iter()
+            .filter_map(|s| Some((s.source.tab_id?, s.tab_sink.clone()?)))
+            .collect()
+    }
+
+    /// Stop the capture a track belongs to (`track.stop()`); its other
+    /// tracks end too
+    pub fn stop_track(&mut self, track_id: &str) {
+        self.end_sessions(|s| s.video_track == track_id || s.audio_track.as_deref() == Some(track_id));
+    }
+
+    /// Stop every capture of a stream
+    pub fn stop_stream(&mut self, stream_id: &str) {
+        self.end_sessions(|s| s.stream_id == stream_id);
+    }
+
+    /// Stop capturing a tab that is closing
+    pub fn stop_tab_source(&mut self, tab_id: u64) {
+        self.end_sessions(|s| s.source.tab_id == Some(tab_id));
+    }
+
+    fn end_sessions(&mut self, matches: impl Fn(&CaptureSession) -> bool) {
+        for session in self.sessions.iter_mut().filter(|s| matches(s)) {
+            session.backend.stop();
+        }
+        self.sessions.retain(|s| !matches(s));
+        self.capturing = !self.sessions.is_empty();
+        if self.stream_id.as_ref().is_some_and(|id| !self.sessions.iter().any(|s| &s.stream_id == id)) {
+            self.stream_id = self.sessions.last().map(|s| s.stream_id.clone());
+        }
+    }
+
     /// Stop capture
     pub fn stop(&mut self) {
-        self.capturing = false;
-        self.stream_id = None;
+        self.end_sessions(|_| true);
     }
 }
 
@@ -95,18 +241,51 @@ pub enum ScreenCaptureError {
     NotAllowed,
     NotSupported,
     AbortError,
+    /// No capturable source exists
+    NotFound,
+    /// The source could not be read (e.g. the OS refused capture)
+    NotReadable,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+    use crate::capture::NullCapture;
+
+    fn null_backend() -> Box<dyn ScreenCaptureBackend> { Box::new(NullCapture::new()) }
+
     #[test]
     fn test_screen_capture() {
-        let mut capture = ScreenCapture::new();
+        let mut capture = ScreenCapture::with_backend_factory(null_backend);
         let stream = capture.get_display_media(DisplayMediaStreamOptions::default()).unwrap();
-        
+
         assert!(capture.capturing);
         assert!(!stream.get_video_tracks().is_empty());
     }
+
+    #[test]
+    fn test_picker_flow() {
+        let mut capture = ScreenCapture::with_backend_factory(null_backend);
+        let options = DisplayMediaStreamOptions {
+            video: Some(DisplayMediaVideoOptions { display_surface: Some(DisplaySurface::Browser), ..Default::default() }),
+            audio: Some(true),
+        };
+        let request = capture.request(options, vec![CaptureSource::tab(7, "News", 800, 600)]).unwrap();
+        assert_eq!(request.sources[0].tab_id, Some(7));
+        assert!(matches!(capture.start(&request, None), Err(ScreenCaptureError::NotAllowed)));
+
+        let stream = capture.start(&request, Some("tab:7")).unwrap();
+        assert_eq!(stream.get_audio_tracks().len(), 1);
+        let video = stream.get_video_tracks()[0].id.clone();
+        assert_eq!(video, capture.sessions[0].video_track);
+
+        let (tab, sink) = capture.captured_tabs().pop().unwrap();
+        assert_eq!(tab, 7);
+        sink.push(CapturedFrame::from_argb(&[0; 4], 2, 2, std::time::Duration::ZERO));
+        assert_eq!(capture.read_frame(&video).unwrap().width, 2);
+
+        capture.stop_tab_source(7);
+        assert_eq!(capture.track_state(&video), MediaStreamTrackState::Ended);
+        assert!(!capture.capturing);
+    }
 }
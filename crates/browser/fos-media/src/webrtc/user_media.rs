@@ -0,0 +1,495 @@
+//! User Media
+//!
+//! `getUserMedia` over the camera and microphone backends. Constraints are
+//! resolved with the spec's fitness distance: every (device, format)
+//! candidate that satisfies the required constraints (`exact`, `min`,
+//! `max`) is scored against the `ideal` values and the closest one wins.
+//! Permission prompts are the browser's job; this module only captures.
+
+use super::connection::{MediaStream, MediaStreamTrack, MediaStreamTrackKind, MediaStreamTrackState};
+use crate::capture::{
+    create_device_backend, CaptureDevice, CaptureDeviceBackend, CaptureFormat,
+    CapturedFrame, DeviceCapabilities, FacingMode, MediaDeviceInfo, MediaDeviceKind,
+};
+
+/// Numeric constraint (`{ min, max, exact, ideal }`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConstrainRange {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub exact: Option<f64>,
+    pub ideal: Option<f64>,
+}
+
+impl ConstrainRange {
+    pub fn ideal(value: f64) -> Self {
+        Self { ideal: Some(value), ..Default::default() }
+    }
+
+    pub fn exact(value: f64) -> Self {
+        Self { exact: Some(value), ..Default::default() }
+    }
+
+    /// Range constraint
+    pub fn between(min: f64, max: f64) -> Self {
+        Self { min: Some(min), max: Some(max), ..Default::default() }
+    }
+
+    fn satisfied(&self, value: f64) -> bool {
+        self.exact.is_none_or(|e| (e - value).abs() < 1e-6)
+            && self.min.is_none_or(|min| value >= min)
+            && self.max.is_none_or(|max| value <= max)
+    }
+
+    fn distance(&self, value: f64) -> f64 {
+        match self.ideal {
+            Some(ideal) if ideal != value => (ideal - value).abs() / ideal.abs().max(value.abs()),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Discrete constraint (`{ exact, ideal }`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConstrainValue<T> {
+    pub exact: Option<T>,
+    pub ideal: Option<T>,
+}
+
+impl<T: PartialEq> ConstrainValue<T> {
+    pub fn ideal(value: T) -> Self {
+        Self { exact: None, ideal: Some(value) }
+    }
+
+    pub fn exact(value: T) -> Self {
+        Self { exact: Some(value), ideal: None }
+    }
+
+    fn satisfied(&self, value: Option<&T>) -> bool {
+        self.exact.as_ref().is_none_or(|e| value == Some(e))
+    }
+
+    fn distance(&self, value: Option<&T>) -> f64 {
+        match &self.ideal {
+            Some(ideal) if value != Some(ideal) => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Constraints for one track
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaTrackConstraints {
+    pub device_id: Option<ConstrainValue<String>>,
+    pub group_id: Option<ConstrainValue<String>>,
+    pub width: Option<ConstrainRange>,
+    pub height: Option<ConstrainRange>,
+    pub frame_rate: Option<ConstrainRange>,
+    pub facing_mode: Option<ConstrainValue<FacingMode>>,
+    pub sample_rate: Option<ConstrainRange>,
+    pub channel_count: Option<ConstrainRange>,
+    pub echo_cancellation: Option<ConstrainValue<bool>>,
+}
+
+/// `getUserMedia` argument; `None` means the kind is not requested
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaStreamConstraints {
+    pub audio: Option<MediaTrackConstraints>,
+    pub video: Option<MediaTrackConstraints>,
+}
+
+/// Settings a track was started with (`getSettings()`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaTrackSettings {
+    pub device_id: String,
+    pub group_id: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub facing_mode: Option<FacingMode>,
+    pub sample_rate: Option<u32>,
+    pub channel_count: Option<u16>,
+    pub echo_cancellation: Option<bool>,
+}
+
+/// User media error
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserMediaError {
+    /// Neither audio nor video requested
+    TypeError,
+    /// The user or the browser denied access
+    NotAllowed,
+    /// No device of a requested kind exists
+    NotFound,
+    /// A device exists but could not be started (busy, OS-level denial)
+    NotReadable,
+    /// No device satisfies a required constraint; carries its name
+    Overconstrained(String),
+}
+
+/// A candidate device with its format and fitness distance
+struct Candidate {
+    info: MediaDeviceInfo,
+    format: CaptureFormat,
+    settings: MediaTrackSettings,
+    distance: f64,
+}
+
+/// Distance of a value from a numeric constraint, or `None` if it violates
+/// a required part
+fn range_fitness(constraint: Option<&ConstrainRange>, value: Option<f64>) -> Option<f64> {
+    let Some(c) = constraint else { return Some(0.0) };
+    match value {
+        Some(v) => c.satisfied(v).then(|| c.distance(v)),
+        // The device lacks the property: only an ideal can be ignored
+        None if c.exact.is_some() || c.min.is_some() || c.max.is_some() => None,
+        None => Some(if c.ideal.is_some() { 1.0 } else { 0.0 }),
+    }
+}
+
+fn value_fitness<T: PartialEq>(constraint: Option<&ConstrainValue<T>>, value: Option<&T>) -> Option<f64> {
+    let Some(c) = constraint else { return Some(0.0) };
+    c.satisfied(value).then(|| c.distance(value))
+}
+
+/// Fitness distance of a (device, format), or the name of the first
+/// required constraint it violates
+fn fitness(constraints: &MediaTrackConstraints, settings: &MediaTrackSettings) -> Result<f64, &'static str> {
+    let c = constraints;
+    let scores = [
+        ("deviceId", value_fitness(c.device_id.as_ref(), Some(&settings.device_id))),
+        ("groupId", value_fitness(c.group_id.as_ref(), Some(&settings.group_id))),
+        ("facingMode", value_fitness(c.facing_mode.as_ref(), settings.facing_mode.as_ref())),
+        ("echoCancellation", value_fitness(c.echo_cancellation.as_ref(), settings.echo_cancellation.as_ref())),
+        ("width", range_fitness(c.width.as_ref(), settings.width.map(f64::from))),
+        ("height", range_fitness(c.height.as_ref(), settings.height.map(f64::from))),
+        ("frameRate", range_fitness(c.frame_rate.as_ref(), settings.frame_rate)),
+        ("sampleRate", range_fitness(c.sample_rate.as_ref(), settings.sample_rate.map(f64::from))),
+        ("channelCount", range_fitness(c.channel_count.as_ref(), settings.channel_count.map(f64::from))),
+    ];
+    scores.iter().try_fold(0.0, |total, (name, score)| score.map(|s| total + s).ok_or(*name))
+}
+
+/// Formats (and their settings) a device can be started in
+fn device_formats(info: &MediaDeviceInfo, caps: &DeviceCapabilities) -> Vec<(CaptureFormat, MediaTrackSettings)> {
+    let base = MediaTrackSettings {
+        device_id: info.device_id.clone(),
+        group_id: info.group_id.clone(),
+        ..Default::default()
+    };
+    match info.kind {
+        MediaDeviceKind::VideoInput => caps.video_formats.iter().map(|f| {
+            (CaptureFormat::Video(*f), MediaTrackSettings {
+                width: Some(f.width),
+                height: Some(f.height),
+                frame_rate: Some(f.frame_rate),
+                facing_mode: caps.facing_mode,
+                ..base.clone()
+            })
+        }).collect(),
+        _ => {
+            let echo = if caps.echo_cancellation { vec![true, false] } else { vec![false] };
+            caps.audio_formats.iter()
+                .flat_map(|f| echo.iter().map(move |&e| (*f, e)))
+                .map(|(format, echo_cancellation)| {
+                    (CaptureFormat::Audio { format, echo_cancellation }, MediaTrackSettings {
+                        sample_rate: Some(format.sample_rate),
+                        channel_count: Some(format.channel_count),
+                        echo_cancellation: Some(echo_cancellation),
+                        ..base.clone()
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+/// A running device behind a track
+struct DeviceSession {
+    stream_id: String,
+    track_id: String,
+    device: Box<dyn CaptureDevice>,
+    settings: MediaTrackSettings,
+}
+
+/// Camera and microphone capture manager
+pub struct UserMedia {
+    backend: Box<dyn CaptureDeviceBackend>,
+    sessions: Vec<DeviceSession>,
+}
+
+impl std::fmt::Debug for UserMedia {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserMedia")
+            .field("backend", &self.backend.name())
+            .field("devices", &self.sessions.iter().map(|s| &s.settings.device_id).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Default for UserMedia {
+    fn default() -> Self { Self::new() }
+}
+
+impl UserMedia {
+    pub fn new() -> Self { Self::with_backend(create_device_backend()) }
+
+    pub fn with_backend(backend: Box<dyn CaptureDeviceBackend>) -> Self {
+        Self { backend, sessions: Vec::new() }
+    }
+
+    /// Attached cameras and microphones. Kinds the page may not capture are
+    /// reduced to one anonymous entry, so devices cannot be fingerprinted
+    /// before permission is granted.
+    pub fn enumerate_devices(&self, exposed: impl Fn(MediaDeviceKind) -> bool) -> Vec<MediaDeviceInfo> {
+        let mut devices = Vec::new();
+        let mut hidden_kinds = Vec::new();
+        for device in self.backend.enumerate_devices() {
+            if exposed(device.kind) {
+                devices.push(device);
+            } else if !hidden_kinds.contains(&device.kind) {
+                hidden_kinds.push(device.kind);
+                devices.push(MediaDeviceInfo { device_id: String::new(), label: String::new(), group_id: String::new(), ..device });
+            }
+        }
+        devices
+    }
+
+    /// Capabilities of a device (`getCapabilities()`)
+    pub fn capabilities(&self, device_id: &str) -> Option<DeviceCapabilities> {
+        self.backend.capabilities(device_id)
+    }
+
+    /// Best (device, format) of a kind for the constraints
+    fn select(&self, kind: MediaDeviceKind, constraints: &MediaTrackConstraints) -> Result<Candidate, UserMediaError> {
+        let devices: Vec<_> = self.backend.enumerate_devices().into_iter().filter(|d| d.kind == kind).collect();
+        if devices.is_empty() {
+            return Err(UserMediaError::NotFound);
+        }
+        let mut best: Option<Candidate> = None;
+        let mut failed = None;
+        for info in devices {
+            let Some(caps) = self.backend.capabilities(&info.device_id) else { continue };
+            for (format, settings) in device_formats(&info, &caps) {
+                match fitness(constraints, &settings) {
+                    Ok(distance) if best.as_ref().is_none_or(|b| distance < b.distance) => {
+                        best = Some(Candidate { info: info.clone(), format, settings, distance });
+                    }
+                    Ok(_) => {}
+                    Err(name) => { failed.get_or_insert(name); }
+                }
+            }
+        }
+        best.ok_or_else(|| UserMediaError::Overconstrained(failed.unwrap_or_default().to_string()))
+    }
+
+    /// `getUserMedia`: start the best matching camera and/or microphone
+    pub fn get_user_media(&mut self, constraints: &MediaStreamConstraints) -> Result<MediaStream, UserMediaError> {
+        if constraints.audio.is_none() && constraints.video.is_none() {
+            return Err(UserMediaError::TypeError);
+        }
+        // Resolve every requested kind before starting anything
+        let mut chosen = Vec::new();
+        if let Some(audio) = &constraints.audio {
+            chosen.push((MediaDeviceKind::AudioInput, self.select(MediaDeviceKind::AudioInput, audio)?));
+        }
+        if let Some(video) = &constraints.video {
+            chosen.push((MediaDeviceKind::VideoInput, self.select(MediaDeviceKind::VideoInput, video)?));
+        }
+
+        let mut stream = MediaStream::new();
+        let mut started: Vec<DeviceSession> = Vec::new();
+        for (kind, candidate) in chosen {
+            let mut device = self.backend.open(&candidate.info.device_id).map_err(|_| UserMediaError::NotReadable)?;
+            if device.start(candidate.format).is_err() {
+                for mut session in started {
+                    session.device.stop();
+                }
+                return Err(UserMediaError::NotReadable);
+            }
+            let (track_kind, suffix) = match kind {
+                MediaDeviceKind::VideoInput => (MediaStreamTrackKind::Video, "video"),
+                _ => (MediaStreamTrackKind::Audio, "audio"),
+            };
+            let track_id = format!("{}-{}", stream.id, suffix);
+            stream.add_track(MediaStreamTrack {
+                id: track_id.clone(),
+                kind: track_kind,
+                label: candidate.info.label.clone(),
+                enabled: true,
+                muted: false,
+                ready_state: MediaStreamTrackState::Live,
+            });
+            started.push(DeviceSession { stream_id: stream.id.clone(), track_id, device, settings: candidate.settings });
+        }
+        self.sessions.extend(started);
+        Ok(stream)
+    }
+
+    /// `track.applyConstraints()`: renegotiate the format on the same device
+    pub fn apply_constraints(&mut self, track_id: &str, constraints: &MediaTrackConstraints) -> Result<(), UserMediaError> {
+        let session = self.sessions.iter().find(|s| s.track_id == track_id).ok_or(UserMediaError::NotFound)?;
+        let pinned = MediaTrackConstraints {
+            device_id: Some(ConstrainValue::exact(session.settings.device_id.clone())),
+            ..constraints.clone()
+        };
+        let kind = session.device.info().kind;
+        let candidate = self.select(kind, &pinned)?;
+        let session = self.sessions.iter_mut().find(|s| s.track_id == track_id).ok_or(UserMediaError::NotFound)?;
+        session.device.start(candidate.format).map_err(|_| UserMediaError::NotReadable)?;
+        session.settings = candidate.settings;
+        Ok(())
+    }
+
+    /// `track.getSettings()`
+    pub fn settings(&self, track_id: &str) -> Option<&MediaTrackSettings> {
+        self.sessions.iter().find(|s| s.track_id == track_id).map(|s| &s.settings)
+    }
+
+    /// Next camera frame of a video track
+    pub fn read_frame(&mut self, track_id: &str) -> Option<CapturedFrame> {
+        self.sessions.iter_mut().find(|s| s.track_id == track_id)?.device.read_frame()
+    }
+
+    /// Next interleaved microphone samples of an audio track
+    pub fn read_audio(&mut self, track_id: &str, out: &mut [f32]) -> usize {
+        self.sessions.iter_mut()
+            .find(|s| s.track_id == track_id)
+            .map(|s| s.device.read_audio(out))
+            .unwrap_or(0)
+    }
+
+    pub fn track_state(&self, track_id: &str) -> MediaStreamTrackState {
+        if self.sessions.iter().any(|s| s.track_id == track_id) {
+            MediaStreamTrackState::Live
+        } else {
+            MediaStreamTrackState::Ended
+        }
+    }
+
+    pub fn is_stream_live(&self, stream_id: &str) -> bool {
+        self.sessions.iter().any(|s| s.stream_id == stream_id)
+    }
+
+    /// Kinds of the devices in use by a stream (for camera/microphone indicators)
+    pub fn stream_kinds(&self, stream_id: &str) -> Vec<MediaDeviceKind> {
+        self.sessions.iter().filter(|s| s.stream_id == stream_id).map(|s| s.device.info().kind).collect()
+    }
+
+    /// `track.stop()`
+    pub fn stop_track(&mut self, track_id: &str) {
+        self.end_sessions(|s| s.track_id == track_id);
+    }
+
+    /// Stop every track of a stream
+    pub fn stop_stream(&mut self, stream_id: &str) {
+        self.end_sessions(|s| s.stream_id == stream_id);
+    }
+
+    pub fn stop(&mut self) {
+        self.end_sessions(|_| true);
+    }
+
+    fn end_sessions(&mut self, matches: impl Fn(&DeviceSession) -> bool) {
+        for session in self.sessions.iter_mut().filter(|s| matches(s)) {
+            session.device.stop();
+        }
+        self.sessions.retain(|s| !matches(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::NullDevices;
+
+    fn user_media() -> UserMedia { UserMedia::with_backend(Box::new(NullDevices::new())) }
+
+    #[test]
+    fn test_get_user_media() {
+        let mut media = user_media();
+        assert_eq!(media.get_user_media(&MediaStreamConstraints::default()).unwrap_err(), UserMediaError::TypeError);
+
+        let constraints = MediaStreamConstraints {
+            audio: Some(MediaTrackConstraints::default()),
+            video: Some(MediaTrackConstraints {
+                width: Some(ConstrainRange::ideal(1280.0)),
+                height: Some(ConstrainRange::ideal(720.0)),
+                ..Default::default()
+            }),
+        };
+        let stream = media.get_user_media(&constraints).unwrap();
+        assert_eq!(stream.get_video_tracks().len(), 1);
+        assert_eq!(stream.get_audio_tracks().len(), 1);
+
+        let video = stream.get_video_tracks()[0].id.clone();
+        let settings = media.settings(&video).unwrap();
+        assert_eq!((settings.width, settings.height), (Some(1280), Some(720)));
+        assert_eq!(media.read_frame(&video).unwrap().width, 1280);
+
+        let audio = stream.get_audio_tracks()[0].id.clone();
+        let mut samples = [0.0f32; 64];
+        assert!(media.read_audio(&audio, &mut samples) > 0);
+
+        media.stop_track(&video);
+        assert_eq!(media.track_state(&video), MediaStreamTrackState::Ended);
+        assert!(media.is_stream_live(&stream.id));
+        media.stop_stream(&stream.id);
+        assert!(!media.is_stream_live(&stream.id));
+    }
+
+    #[test]
+    fn test_constraints() {
+        let mut media = user_media();
+        let overconstrained = MediaStreamConstraints {
+            video: Some(MediaTrackConstraints { width: Some(ConstrainRange { min: Some(3840.0), ..Default::default() }), ..Default::default() }),
+            ..Default::default()
+        };
+        assert_eq!(media.get_user_media(&overconstrained).unwrap_err(), UserMediaError::Overconstrained("width".into()));
+
+        let facing = MediaStreamConstraints {
+            video: Some(MediaTrackConstraints { facing_mode: Some(ConstrainValue::exact(FacingMode::Environment)), ..Default::default() }),
+            ..Default::default()
+        };
+        assert_eq!(media.get_user_media(&facing).unwrap_err(), UserMediaError::Overconstrained("facingMode".into()));
+
+        // Frame rate range rules out 1080p at 15 fps
+        let stream = media.get_user_media(&MediaStreamConstraints {
+            video: Some(MediaTrackConstraints {
+                width: Some(ConstrainRange::ideal(1920.0)),
+                frame_rate: Some(ConstrainRange::between(24.0, 60.0)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }).unwrap();
+        let track = stream.tracks[0].id.clone();
+        assert_eq!(media.settings(&track).unwrap().width, Some(1280));
+
+        media.apply_constraints(&track, &MediaTrackConstraints { width: Some(ConstrainRange::exact(320.0)), ..Default::default() }).unwrap();
+        assert_eq!(media.settings(&track).unwrap().height, Some(240));
+        assert!(media.apply_constraints(&track, &MediaTrackConstraints { width: Some(ConstrainRange::exact(100.0)), ..Default::default() }).is_err());
+        assert_eq!(media.settings(&track).unwrap().width, Some(320));
+
+        let mic = MediaStreamConstraints {
+            audio: Some(MediaTrackConstraints {
+                echo_cancellation: Some(ConstrainValue::ideal(false)),
+                channel_count: Some(ConstrainRange::exact(2.0)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let stream = media.get_user_media(&mic).unwrap();
+        let settings = media.settings(&stream.tracks[0].id).unwrap();
+        assert_eq!((settings.channel_count, settings.echo_cancellation), (Some(2), Some(false)));
+    }
+
+    #[test]
+    fn test_enumerate_devices_before_permission() {
+        let media = user_media();
+        let devices = media.enumerate_devices(|kind| kind == MediaDeviceKind::AudioInput);
+        assert_eq!(devices.len(), 2);
+        let camera = devices.iter().find(|d| d.kind == MediaDeviceKind::VideoInput).unwrap();
+        assert!(camera.label.is_empty() && camera.device_id.is_empty());
+        assert_eq!(devices.iter().find(|d| d.kind == MediaDeviceKind::AudioInput).unwrap().label, "Test Microphone");
+    }
+}
@@ -0,0 +1,204 @@
+//! Script Cookie Access
+//!
+//! Checks on `document.cookie`: scripts never see HttpOnly cookies and
+//! cannot create them, Secure cookies need a secure context, `SameSite=None`
+//! needs `Secure`, oversized cookies are refused and a missing `SameSite`
+//! defaults to `Lax`. Rejections and warnings carry a message for the
+//! devtools console.
+
+use crate::https::SecureContext;
+use crate::privacy::CookiePolicy;
+
+/// Maximum bytes of a cookie's name and value together
+pub const MAX_NAME_VALUE_SIZE: usize = 4096;
+
+/// Maximum bytes of one attribute value
+pub const MAX_ATTRIBUTE_VALUE_SIZE: usize = 1024;
+
+/// Why a cookie written from script was refused
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CookieRejection {
+    #[error("Cookie \"{0}\" was blocked by the cookie policy")]
+    BlockedByPolicy(String),
+
+    #[error("Cookie \"{0}\" was rejected: name and value exceed {} bytes", MAX_NAME_VALUE_SIZE)]
+    TooLarge(String),
+
+    #[error("Cookie \"{0}\" was rejected: attribute \"{1}\" exceeds {} bytes", MAX_ATTRIBUTE_VALUE_SIZE)]
+    AttributeTooLarge(String, String),
+
+    #[error("Cookie \"{0}\" was rejected: HttpOnly cookies cannot be set from script")]
+    HttpOnly(String),
+
+    #[error("Cookie \"{0}\" was rejected: Secure cookies require a secure context")]
+    InsecureContext(String),
+
+    #[error("Cookie \"{0}\" was rejected: SameSite=None requires the Secure attribute")]
+    SameSiteNoneInsecure(String),
+
+    #[error("Cookie \"{0}\" was rejected: its name prefix requirements are not met")]
+    InvalidPrefix(String),
+
+    #[error("Cookie string is malformed")]
+    Malformed,
+}
+
+/// Non-fatal issue with a cookie written from script
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CookieWarning {
+    #[error("Cookie \"{0}\" has no SameSite attribute and was treated as SameSite=Lax")]
+    SameSiteDefaulted(String),
+}
+
+/// Security state of a document accessing its cookies
+#[derive(Debug, Clone)]
+pub struct CookieAccess {
+    secure: bool,
+    policy: CookiePolicy,
+    third_party: bool,
+    /// Embedded document granted storage access
+    storage_access: bool,
+}
+
+impl CookieAccess {
+    /// Access from a document at `url`
+    pub fn new(url: &str) -> Self {
+        Self {
+            secure: SecureContext::new(url).check(),
+            policy: CookiePolicy::default(),
+            third_party: false,
+            storage_access: false,
+        }
+    }
+
+    /// Apply a cookie policy
+    pub fn with_policy(mut self, policy: CookiePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Mark the document as embedded cross-site
+    pub fn third_party(mut self, third_party: bool) -> Self {
+        self.third_party = third_party;
+        self
+    }
+
+    /// Mark an embedded document as granted its unpartitioned cookies
+    /// through the Storage Access API
+    pub fn with_storage_access(mut self, granted: bool) -> Self {
+        self.storage_access = granted;
+        self
+    }
+
+    /// Whether the document is a secure context
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Whether the cookie policy lets the document use cookies at all
+    pub fn is_allowed(&self) -> bool {
+        match self.policy {
+            CookiePolicy::AcceptAll => true,
+            CookiePolicy::BlockThirdParty => !self.third_party || self.storage_access,
+            CookiePolicy::BlockAll => false,
+        }
+    }
+
+    /// Whether a stored cookie may appear in `document.cookie`
+    pub fn can_read(&self, http_only: bool, secure: bool) -> bool {
+        self.is_allowed() && !http_only && (!secure || self.secure)
+    }
+
+    /// Check a string assigned to `document.cookie`
+    pub fn check_write(&self, cookie: &str) -> Result<Vec<CookieWarning>, CookieRejection> {
+        let mut parts = cookie.split(';');
+        let name_value = parts.next().unwrap_or("").trim();
+        let name = name_value.split_once('=').ok_or(CookieRejection::Malformed)?.0.trim().to_string();
+        if !self.is_allowed() {
+            return Err(CookieRejection::BlockedByPolicy(name));
+        }
+        if name_value.len() > MAX_NAME_VALUE_SIZE {
+            return Err(CookieRejection::TooLarge(name));
+        }
+
+        let (mut secure, mut same_site, mut domain, mut path) = (false, None, false, None);
+        for part in parts {
+            let (attribute, value) = match part.split_once('=') {
+                Some((attribute, value)) => (attribute.trim(), Some(value.trim())),
+                None => (part.trim(), None),
+            };
+            if value.is_some_and(|v| v.len() > MAX_ATTRIBUTE_VALUE_SIZE) {
+                return Err(CookieRejection::AttributeTooLarge(name, attribute.to_string()));
+            }
+            match attribute.to_ascii_lowercase().as_str() {
+                "httponly" => return Err(CookieRejection::HttpOnly(name)),
+                "secure" => secure = true,
+                // Partitioned implies Secure
+                "partitioned" => secure = true,
+                "samesite" => same_site = value.map(str::to_ascii_lowercase),
+                "domain" => domain = true,
+                "path" => path = value,
+                _ => {}
+            }
+        }
+
+        if secure && !self.secure {
+            return Err(CookieRejection::InsecureContext(name));
+        }
+        if same_site.as_deref() == Some("none") && !secure {
+            return Err(CookieRejection::SameSiteNoneInsecure(name));
+        }
+        let host_prefix_ok = secure && !domain && path == Some("/");
+        if (name.starts_with("__Secure-") && !secure) || (name.starts_with("__Host-") && !host_prefix_ok) {
+            return Err(CookieRejection::InvalidPrefix(name));
+        }
+
+        let mut warnings = Vec::new();
+        if same_site.is_none() {
+            warnings.push(CookieWarning::SameSiteDefaulted(name));
+        }
+        Ok(warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_write() {
+        let secure = CookieAccess::new("https://example.com/");
+        assert_eq!(secure.check_write("a=1; SameSite=Strict"), Ok(Vec::new()));
+        assert_eq!(
+            secure.check_write("a=1"),
+            Ok(vec![CookieWarning::SameSiteDefaulted("a".into())])
+        );
+        assert_eq!(secure.check_write("a=1; HttpOnly"), Err(CookieRejection::HttpOnly("a".into())));
+        assert_eq!(
+            secure.check_write("a=1; SameSite=None"),
+            Err(CookieRejection::SameSiteNoneInsecure("a".into()))
+        );
+        assert!(secure.check_write("__Host-id=1; Secure; Path=/; SameSite=Lax").is_ok());
+        assert!(secure.check_write("__Host-id=1; Secure; Domain=example.com; Path=/").is_err());
+        let huge = format!("big={}", "x".repeat(MAX_NAME_VALUE_SIZE));
+        assert_eq!(secure.check_write(&huge), Err(CookieRejection::TooLarge("big".into())));
+
+        let insecure = CookieAccess::new("http://example.com/");
+        assert_eq!(insecure.check_write("a=1; Secure"), Err(CookieRejection::InsecureContext("a".into())));
+    }
+
+    #[test]
+    fn test_read_and_policy() {
+        let insecure = CookieAccess::new("http://example.com/");
+        assert!(insecure.can_read(false, false));
+        assert!(!insecure.can_read(false, true));
+        assert!(!insecure.can_read(true, false));
+
+        let embedded = CookieAccess::new("https://tracker.example/").third_party(true);
+        assert!(!embedded.can_read(false, false));
+        assert!(matches!(embedded.check_write("id=1"), Err(CookieRejection::BlockedByPolicy(_))));
+        assert!(embedded.clone().with_storage_access(true).can_read(false, false));
+        assert!(!embedded.clone().with_storage_access(true).with_policy(CookiePolicy::BlockAll).is_allowed());
+        assert!(embedded.with_policy(CookiePolicy::AcceptAll).is_allowed());
+    }
+}
@@ -113,6 +113,18 @@ impl ContentSecurityPolicy {
         }
     }
     
+    /// Check if a worker script is allowed: `worker-src`, falling back to
+    /// `child-src`, `script-src` and `default-src`
+    pub fn allows_worker(&self, source: &str, origin: &str) -> bool {
+        let values = [WORKER_SRC, CHILD_SRC, SCRIPT_SRC, DEFAULT_SRC]
+            .iter()
+            .find_map(|directive| self.directives.get(*directive));
+        match values {
+            Some(values) => values.iter().any(|value| self.source_matches(value, source, origin)),
+            None => true,
+        }
+    }
+    
     /// Check if inline scripts allowed
     pub fn allows_inline_script(&self) -> bool {
         self.get(SCRIPT_SRC)
@@ -1,6 +1,10 @@
 //! HTTPS and Secure Contexts
 //!
-//! HTTPS enforcement and mixed content blocking.
+//! HTTPS enforcement, HTTP Strict Transport Security and mixed content
+//! blocking.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 /// Secure context
 #[derive(Debug, Clone)]
@@ -117,6 +121,75 @@ pub enum MixedContentResult {
     Upgrade,
 }
 
+/// Strict Transport Security policy of a host
+#[derive(Debug, Clone)]
+struct HstsEntry {
+    expires: Instant,
+    include_subdomains: bool,
+}
+
+/// Hosts only reachable over HTTPS, learned from `Strict-Transport-Security`
+///
+/// Certificate errors on these hosts cannot be overridden.
+#[derive(Debug, Clone, Default)]
+pub struct HstsStore {
+    hosts: HashMap<String, HstsEntry>,
+}
+
+impl HstsStore {
+    pub fn new() -> Self { Self::default() }
+    
+    /// Record the `Strict-Transport-Security` header of a response from
+    /// `host` over a valid HTTPS connection; `max-age=0` forgets the host
+    pub fn record(&mut self, host: &str, header: &str) {
+        let mut max_age = None;
+        let mut include_subdomains = false;
+        for directive in header.split(';').map(str::trim) {
+            let (name, value) = directive.split_once('=').unwrap_or((directive, ""));
+            if name.eq_ignore_ascii_case("max-age") {
+                max_age = value.trim_matches('"').parse::<u64>().ok();
+            } else if name.eq_ignore_ascii_case("includeSubDomains") {
+                include_subdomains = true;
+            }
+        }
+        let Some(max_age) = max_age else { return };
+        let host = host.to_ascii_lowercase();
+        if max_age == 0 {
+            self.hosts.remove(&host);
+        } else {
+            let expires = Instant::now() + Duration::from_secs(max_age);
+            self.hosts.insert(host, HstsEntry { expires, include_subdomains });
+        }
+    }
+    
+    /// Whether `host` has a policy, its own or a parent's including
+    /// subdomains
+    pub fn is_hsts_host(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        let now = Instant::now();
+        let mut domain = host.as_str();
+        loop {
+            if let Some(entry) = self.hosts.get(domain).filter(|e| e.expires > now) {
+                if domain == host || entry.include_subdomains {
+                    return true;
+                }
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) => domain = parent,
+                None => return false,
+            }
+        }
+    }
+    
+    /// `https://` form of an `http://` URL to a host with a policy
+    pub fn upgrade(&self, url: &str) -> Option<String> {
+        let rest = url.strip_prefix("http://")?;
+        let authority = rest.split(['/', '?', '#']).next()?;
+        let host = authority.rsplit_once(':').map_or(authority, |(host, _)| host);
+        self.is_hsts_host(host).then(|| format!("https://{}", rest))
+    }
+}
+
 /// Certificate info
 #[derive(Debug, Clone)]
 pub struct CertificateInfo {
@@ -164,4 +237,23 @@ mod tests {
         
         assert_eq!(result, MixedContentResult::Block);
     }
+    
+    #[test]
+    fn test_hsts() {
+        let mut hsts = HstsStore::new();
+        hsts.record("example.com", "max-age=31536000; includeSubDomains");
+        hsts.record("other.com", "max-age=600");
+        hsts.record("nothing.com", "includeSubDomains");
+        
+        assert!(hsts.is_hsts_host("EXAMPLE.com"));
+        assert!(hsts.is_hsts_host("www.example.com"));
+        assert!(hsts.is_hsts_host("other.com"));
+        assert!(!hsts.is_hsts_host("www.other.com"));
+        assert!(!hsts.is_hsts_host("nothing.com"));
+        assert_eq!(hsts.upgrade("http://www.example.com:80/a").as_deref(), Some("https://www.example.com:80/a"));
+        assert_eq!(hsts.upgrade("http://plain.com/"), None);
+        
+        hsts.record("example.com", "max-age=0");
+        assert!(!hsts.is_hsts_host("www.example.com"));
+    }
 }
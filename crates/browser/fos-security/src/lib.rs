@@ -3,17 +3,23 @@
 //! Security APIs for the fOS browser engine.
 //!
 //! Features:
-//! - Same-Origin Policy and CORS
+//! - Same-Origin Policy, CORS and postMessage target origins
 //! - Content Security Policy
-//! - HTTPS and mixed content
+//! - HTTPS, HSTS and mixed content
 //! - Sandbox
-//! - Privacy (referrer, tracking)
+//! - Privacy (referrer, tracking, third-party content blocking, fingerprinting,
+//!   bounce tracking, GPC/DNT)
+//! - First-party isolation (storage keys, window.name)
+//! - Storage Access API grants for embedded third parties
+//! - Declarative request rules (block, redirect, header changes)
+//! - Script cookie access (document.cookie)
 //! - Subresource Integrity (SRI)
 //! - Permissions Policy
 //! - Trusted Types
 //! - Credential Management
 //! - XSS Protection
 //! - Cross-Origin Isolation (COOP/COEP)
+//! - Policy containers for documents and workers
 
 pub mod origin;
 pub mod csp;
@@ -26,18 +32,28 @@ pub mod trusted_types;
 pub mod credential_api;
 pub mod xss_protection;
 pub mod coop_coep;
+pub mod cookie_access;
+pub mod policy_container;
+pub mod request_rules;
+pub mod partitioning;
+pub mod storage_access;
 
-pub use origin::{Origin, CorsMode, CorsValidator, CorsRequest, CorsResponse};
+pub use origin::{Origin, TargetOrigin, CorsMode, CorsValidator, CorsRequest, CorsResponse};
 pub use csp::{ContentSecurityPolicy, CspViolation};
-pub use https::{SecureContext, MixedContentChecker, MixedContentResult};
+pub use https::{SecureContext, MixedContentChecker, MixedContentResult, HstsStore};
 pub use sandbox::{SandboxFlags, SandboxFlag};
-pub use privacy::{ReferrerPolicy, CookiePolicy, TrackingProtection};
+pub use privacy::{ReferrerPolicy, CookiePolicy, TrackingProtection, TRACKING_PROTECTION_SOURCE, ContentBlocking, ContentCategory, CONTENT_BLOCKING_SOURCE, FingerprintingProtection, BounceTracking, BounceTrackingConfig, BounceTrackingStats, PrivacySignals, DoNotTrack};
 pub use subresource_integrity::{SriValidator, IntegrityMetadata, IntegrityAlgorithm, SriResult};
 pub use permissions_policy::{PermissionsPolicy, Feature, Allowlist};
 pub use trusted_types::{TrustedTypePolicyFactory, TrustedType, TrustedTypesEnforcer};
 pub use credential_api::{CredentialManager, Credential, PasswordCredential};
 pub use xss_protection::{Sanitizer, SanitizerConfig, XssDetector};
 pub use coop_coep::{CrossOriginIsolation, CoopPolicy, CoepPolicy, IsolationEnforcer};
+pub use cookie_access::{CookieAccess, CookieRejection, CookieWarning};
+pub use policy_container::{PolicyContainer, WorkerKind, WorkerPolicyError};
+pub use partitioning::{StorageKey, WindowNamePartition};
+pub use storage_access::{StorageAccess, StorageAccessConfig, StorageAccessResult};
+pub use request_rules::{RuleMatcher, Rule, RuleAction, RuleCondition, RuleOutcome, RuleVerdict, RuleError, RequestDetails, ResourceType, DomainType, HeaderOperation, HeaderOp};
 
 /// Security error
 #[derive(Debug, Clone, thiserror::Error)]
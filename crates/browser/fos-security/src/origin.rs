@@ -1,6 +1,6 @@
 //! Origin and Same-Origin Policy
 //!
-//! Web origin model and CORS implementation.
+//! Web origin model, `postMessage` target origins and CORS implementation.
 
 /// Web Origin
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -87,6 +87,43 @@ impl Origin {
     }
 }
 
+/// Receivers a `postMessage` call allows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetOrigin {
+    /// `*`: any receiver
+    Any,
+    /// Only receivers of this origin
+    Origin(Origin),
+    /// `/` sent from an opaque origin, which no receiver matches
+    Opaque,
+}
+
+impl TargetOrigin {
+    /// Parse a `targetOrigin` argument. `/` stands for the sender's origin,
+    /// `None` when opaque; anything else must be an absolute URL, whose
+    /// origin is taken. None if it is not, which postMessage throws a
+    /// SyntaxError for
+    pub fn parse(target: &str, sender: Option<&Origin>) -> Option<Self> {
+        match target {
+            "*" => Some(Self::Any),
+            "/" => Some(sender.map_or(Self::Opaque, |origin| Self::Origin(origin.clone()))),
+            _ => Origin::from_url(target).map(Self::Origin),
+        }
+    }
+    
+    /// Whether a document of the `receiver` origin, `None` when opaque,
+    /// gets the message
+    pub fn allows(&self, receiver: Option<&Origin>) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Origin(origin) => receiver.is_some_and(|receiver| {
+                !origin.is_opaque() && !receiver.is_opaque() && origin.is_same_origin(receiver)
+            }),
+            Self::Opaque => false,
+        }
+    }
+}
+
 /// CORS request mode
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum CorsMode {
@@ -209,6 +246,25 @@ mod tests {
         assert!(!o1.is_same_origin(&o3)); // Different scheme
     }
     
+    #[test]
+    fn test_target_origin() {
+        let sender = Origin::from_url("https://a.com/page").unwrap();
+        let same = Origin::new("https", "a.com", Some(443));
+        let other = Origin::new("https", "b.com", None);
+        
+        assert!(TargetOrigin::parse("*", None).unwrap().allows(Some(&other)));
+        let target = TargetOrigin::parse("/", Some(&sender)).unwrap();
+        assert!(target.allows(Some(&same)));
+        assert!(!target.allows(Some(&other)));
+        assert!(!target.allows(None));
+        
+        // Only the origin of a URL counts
+        let target = TargetOrigin::parse("https://b.com/any/path", Some(&sender)).unwrap();
+        assert!(target.allows(Some(&other)));
+        assert!(!TargetOrigin::parse("/", None).unwrap().allows(None));
+        assert_eq!(TargetOrigin::parse("b.com", Some(&sender)), None);
+    }
+    
     #[test]
     fn test_cors_validator() {
         let validator = CorsValidator::new();
@@ -0,0 +1,141 @@
+//! First-Party Isolation
+//!
+//! State that outlives a document or is shared between documents is keyed
+//! by a storage key: the site of the top-level page together with the
+//! origin using the state. An embedded tracker then sees separate blob URLs,
+//! BroadcastChannels and SharedWorkers on every site it is embedded in,
+//! instead of one shared channel to join visits across sites.
+//!
+//! `window.name` survives navigations of a browsing context, so a top-level
+//! context sets its name aside when it navigates cross-site and only gets it
+//! back when it returns to that site.
+
+use crate::Origin;
+use fos_dom::url::{Site, Url};
+use std::collections::HashMap;
+
+/// Serialized schemeful site of a URL; `None` for opaque origins
+pub fn site_of(url: &str) -> Option<String> {
+    Site::from_url(&Url::parse(url.trim()).ok()?).map(|site| site.to_string())
+}
+
+/// Whether two URLs are same-site; opaque origins are same-site with nothing
+pub fn is_same_site(a: &str, b: &str) -> bool {
+    match (site_of(a), site_of(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Partition of shared state: top-level site and origin
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StorageKey {
+    /// Site of the top-level document; `null` if opaque
+    pub top_level_site: String,
+    /// Serialized origin of the document or worker; `null` if opaque
+    pub origin: String,
+}
+
+impl StorageKey {
+    /// Key of a document at `url` embedded in a page at `top_level_url`
+    pub fn new(top_level_url: &str, url: &str) -> Self {
+        Self {
+            top_level_site: site_of(top_level_url).unwrap_or_else(|| "null".to_string()),
+            origin: Origin::from_url(url).map_or_else(|| "null".to_string(), |o| o.serialize()),
+        }
+    }
+
+    /// Key of a top-level document
+    pub fn first_party(url: &str) -> Self {
+        Self::new(url, url)
+    }
+
+    /// Either half is opaque, so the key shares state with no other
+    pub fn is_opaque(&self) -> bool {
+        self.top_level_site == "null" || self.origin == "null"
+    }
+
+    /// The origin is not on the top-level site
+    pub fn is_third_party(&self) -> bool {
+        site_of(&self.origin).as_deref() != Some(self.top_level_site.as_str())
+    }
+
+    /// Whether state from `other` may be shared with this key; opaque keys
+    /// never share, not even with an equal key
+    pub fn same_partition(&self, other: &StorageKey) -> bool {
+        self == other && !self.is_opaque()
+    }
+}
+
+/// `window.name` of a top-level browsing context, partitioned by site
+#[derive(Debug, Clone, Default)]
+pub struct WindowNamePartition {
+    /// Name set aside when the context last left each site
+    names: HashMap<String, String>,
+}
+
+impl WindowNamePartition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name the context has after navigating from `from` to `to` while named
+    /// `name`. Same-site navigations keep it; cross-site ones set it aside
+    /// for the site left and bring back the one set aside for the site
+    /// entered, or clear it.
+    pub fn navigate(&mut self, name: &str, from: &str, to: &str) -> String {
+        if is_same_site(from, to) {
+            return name.to_string();
+        }
+        if let Some(site) = site_of(from) {
+            if name.is_empty() {
+                self.names.remove(&site);
+            } else {
+                self.names.insert(site, name.to_string());
+            }
+        }
+        site_of(to).and_then(|site| self.names.get(&site).cloned()).unwrap_or_default()
+    }
+
+    /// Forget the names set aside, e.g. when history is cleared
+    pub fn clear(&mut self) {
+        self.names.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_key() {
+        let first = StorageKey::first_party("https://news.example.com/story");
+        assert_eq!(first.top_level_site, "https://example.com");
+        assert_eq!(first.origin, "https://news.example.com");
+        assert!(!first.is_third_party());
+
+        // The same tracker gets a different key on each site embedding it
+        let on_a = StorageKey::new("https://a.com/", "https://tracker.net/frame");
+        let on_b = StorageKey::new("https://b.com/", "https://tracker.net/frame");
+        assert!(on_a.is_third_party());
+        assert!(!on_a.same_partition(&on_b));
+        assert!(on_a.same_partition(&StorageKey::new("https://www.a.com/x", "https://tracker.net/other")));
+
+        let opaque = StorageKey::new("https://a.com/", "data:text/html,hi");
+        assert!(opaque.is_opaque());
+        assert!(!opaque.same_partition(&opaque.clone()));
+    }
+
+    #[test]
+    fn test_window_name_partition() {
+        let mut names = WindowNamePartition::new();
+        assert_eq!(names.navigate("id=42", "https://a.com/", "https://www.a.com/next"), "id=42");
+
+        // Leaving the site clears the name; coming back restores it
+        let name = names.navigate("id=42", "https://www.a.com/next", "https://b.com/");
+        assert_eq!(name, "");
+        let name = names.navigate("b-state", "https://b.com/", "https://a.com/again");
+        assert_eq!(name, "id=42");
+        assert_eq!(names.navigate(&name, "https://a.com/again", "https://b.com/"), "b-state");
+    }
+}
@@ -0,0 +1,183 @@
+//! Policy Containers
+//!
+//! The CSP, referrer policy and embedder policy that a document or worker
+//! enforces on its own fetches. A worker loaded from a local URL (`blob:`,
+//! `data:`, `about:`) inherits its creator's container; any other worker
+//! takes its container from the headers of its script response.
+
+use std::collections::HashMap;
+
+use crate::coop_coep::{CoepPolicy, CrossOriginIsolation, IsolationEnforcer};
+use crate::csp::ContentSecurityPolicy;
+use crate::privacy::ReferrerPolicy;
+
+/// Kind of worker global scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorkerKind {
+    Dedicated,
+    Shared,
+    Service,
+}
+
+impl WorkerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dedicated => "worker",
+            Self::Shared => "sharedworker",
+            Self::Service => "serviceworker",
+        }
+    }
+}
+
+/// Why a worker script was refused
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WorkerPolicyError {
+    #[error("Loading the {0} script \"{1}\" violates the worker-src directive")]
+    BlockedByCsp(&'static str, String),
+
+    #[error("Worker script \"{0}\" was blocked: the sandbox does not allow scripts")]
+    Sandboxed(String),
+
+    #[error("Service worker script \"{0}\" must be loaded over HTTPS")]
+    InsecureServiceWorker(String),
+
+    #[error("Worker script \"{0}\" does not send a Cross-Origin-Embedder-Policy compatible with require-corp")]
+    EmbedderPolicyMismatch(String),
+}
+
+/// Policies of a document or worker
+#[derive(Debug, Clone, Default)]
+pub struct PolicyContainer {
+    pub csp: Option<ContentSecurityPolicy>,
+    pub referrer_policy: ReferrerPolicy,
+    pub coep: CoepPolicy,
+}
+
+impl PolicyContainer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Policies delivered with a response; header names are lowercase
+    pub fn from_headers(headers: &HashMap<String, String>) -> Self {
+        let csp = headers.get("content-security-policy").map(|h| ContentSecurityPolicy::parse(h));
+        // The last policy the browser understands wins
+        let referrer_policy = headers
+            .get("referrer-policy")
+            .and_then(|h| h.split(',').rev().find_map(|token| ReferrerPolicy::parse(token.trim())))
+            .unwrap_or_default();
+        let coep = headers.get("cross-origin-embedder-policy").map(|h| CoepPolicy::parse(h)).unwrap_or_default();
+        Self { csp, referrer_policy, coep }
+    }
+
+    /// Check a worker script URL against `worker-src` before fetching it
+    pub fn check_worker_script(&self, kind: WorkerKind, url: &str, origin: &str) -> Result<(), WorkerPolicyError> {
+        if kind == WorkerKind::Service && !url.starts_with("https://") && !is_localhost(url) {
+            return Err(WorkerPolicyError::InsecureServiceWorker(url.to_string()));
+        }
+        match &self.csp {
+            Some(csp) if !csp.allows_worker(url, origin) => Err(WorkerPolicyError::BlockedByCsp(kind.as_str(), url.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Container of a worker created by a document or worker with this
+    /// container, from the worker script's response headers
+    pub fn for_worker(
+        &self,
+        kind: WorkerKind,
+        url: &str,
+        headers: &HashMap<String, String>,
+    ) -> Result<Self, WorkerPolicyError> {
+        if is_local_scheme(url) && kind != WorkerKind::Service {
+            return Ok(self.clone());
+        }
+        let container = Self::from_headers(headers);
+        // A dedicated worker of a require-corp owner must enforce COEP too
+        if kind == WorkerKind::Dedicated && self.coep == CoepPolicy::RequireCorp && container.coep == CoepPolicy::UnsafeNone {
+            return Err(WorkerPolicyError::EmbedderPolicyMismatch(url.to_string()));
+        }
+        Ok(container)
+    }
+
+    /// Whether a fetch for `directive` (e.g. `connect-src`) is allowed
+    pub fn allows(&self, directive: &str, url: &str, origin: &str) -> bool {
+        self.csp.as_ref().is_none_or(|csp| csp.allows(directive, url, origin))
+    }
+
+    /// Referrer to send with a fetch
+    pub fn referrer(&self, source_url: &str, dest_url: &str) -> Option<String> {
+        self.referrer_policy.compute_referrer(source_url, dest_url)
+    }
+
+    /// CORP enforcement for fetches under this container's embedder policy
+    pub fn isolation_enforcer(&self) -> IsolationEnforcer {
+        let mut enforcer = IsolationEnforcer::new();
+        enforcer.set_document_isolation(CrossOriginIsolation { coep: self.coep, ..CrossOriginIsolation::new() });
+        enforcer
+    }
+}
+
+/// URLs whose content does not come from the network
+pub fn is_local_scheme(url: &str) -> bool {
+    ["blob:", "data:", "about:"].iter().any(|scheme| url.get(..scheme.len()).is_some_and(|s| s.eq_ignore_ascii_case(scheme)))
+}
+
+fn is_localhost(url: &str) -> bool {
+    let rest = url.strip_prefix("http://").unwrap_or("");
+    let host = rest.split(['/', ':']).next().unwrap_or("");
+    host == "localhost" || host == "127.0.0.1"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coop_coep::CorpPolicy;
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_worker_src() {
+        let document = PolicyContainer::from_headers(&headers(&[(
+            "content-security-policy",
+            "script-src 'self' https://cdn.example.com; worker-src 'self'",
+        )]));
+        let origin = "https://example.com";
+        assert!(document.check_worker_script(WorkerKind::Dedicated, "https://example.com/w.js", origin).is_ok());
+        assert_eq!(
+            document.check_worker_script(WorkerKind::Shared, "https://cdn.example.com/w.js", origin),
+            Err(WorkerPolicyError::BlockedByCsp("sharedworker", "https://cdn.example.com/w.js".into()))
+        );
+        assert!(document.check_worker_script(WorkerKind::Service, "http://example.com/sw.js", origin).is_err());
+
+        // Without worker-src, script-src applies
+        let fallback = PolicyContainer::from_headers(&headers(&[("content-security-policy", "script-src https://cdn.example.com")]));
+        assert!(fallback.check_worker_script(WorkerKind::Dedicated, "https://cdn.example.com/w.js", origin).is_ok());
+        assert!(fallback.check_worker_script(WorkerKind::Dedicated, "https://example.com/w.js", origin).is_err());
+    }
+
+    #[test]
+    fn test_worker_container() {
+        let document = PolicyContainer::from_headers(&headers(&[
+            ("content-security-policy", "connect-src 'self'"),
+            ("referrer-policy", "no-referrer, unknown-policy"),
+            ("cross-origin-embedder-policy", "require-corp"),
+        ]));
+        assert_eq!(document.referrer_policy, ReferrerPolicy::NoReferrer);
+
+        // Local workers inherit the creator's policies
+        let blob = document.for_worker(WorkerKind::Dedicated, "blob:https://example.com/1", &HashMap::new()).unwrap();
+        assert!(!blob.allows("connect-src", "https://api.other.com/", "https://example.com"));
+
+        // Network workers use their own, and must keep COEP
+        assert!(document.for_worker(WorkerKind::Dedicated, "https://example.com/w.js", &HashMap::new()).is_err());
+        let worker = document
+            .for_worker(WorkerKind::Dedicated, "https://example.com/w.js", &headers(&[("cross-origin-embedder-policy", "require-corp")]))
+            .unwrap();
+        assert!(worker.allows("connect-src", "https://api.other.com/", "https://example.com"));
+        assert_eq!(worker.referrer("https://example.com/w.js", "https://other.com/"), Some("https://example.com".into()));
+        assert!(!worker.isolation_enforcer().can_embed(CorpPolicy::None, "https://other.com", "https://example.com"));
+    }
+}
@@ -1,6 +1,18 @@
 //! Privacy
 //!
-//! Referrer policy, tracking protection, cookie policies.
+//! Referrer policy, tracking protection, third-party content blocking,
+//! cookie policies, fingerprinting resistance, bounce tracking mitigation,
+//! Global Privacy Control and Do Not Track.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use crate::partitioning::site_of;
+
+use crate::request_rules::{
+    host_of, in_domain, DomainType, RequestDetails, ResourceType, Rule, RuleAction, RuleCondition, RuleMatcher, RuleOutcome,
+};
 
 /// Referrer policy
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -105,12 +117,18 @@ pub enum CookiePolicy {
     BlockAll,
 }
 
+/// Source tracking protection's rules compile under in a [`RuleMatcher`]
+pub const TRACKING_PROTECTION_SOURCE: &str = "tracking-protection";
+
 /// Tracking protection
 #[derive(Debug, Clone, Default)]
 pub struct TrackingProtection {
     pub enabled: bool,
     pub strict_mode: bool,
+    /// Domains, optionally with a path prefix; change through
+    /// [`Self::block_domain`] so the rules follow
     pub blocked_domains: Vec<String>,
+    matcher: RuleMatcher,
 }
 
 impl TrackingProtection {
@@ -125,6 +143,7 @@ impl TrackingProtection {
             "facebook.com/tr".into(),
             "analytics.google.com".into(),
         ];
+        self.compile();
     }
     
     /// Check if URL should be blocked
@@ -133,13 +152,480 @@ impl TrackingProtection {
             return false;
         }
         
-        let url_lower = url.to_lowercase();
-        self.blocked_domains.iter().any(|d| url_lower.contains(d))
+        let request = RequestDetails::new(url, ResourceType::Other);
+        self.matcher.evaluate(&request).outcome == RuleOutcome::Block
     }
     
     /// Add domain to blocklist
     pub fn block_domain(&mut self, domain: &str) {
         self.blocked_domains.push(domain.to_lowercase());
+        self.compile();
+    }
+    
+    /// The blocklist as request rules: each entry blocks subresources from
+    /// the domain and its subdomains, or under the path if it has one
+    pub fn rules(&self) -> Vec<Rule> {
+        self.blocked_domains.iter().enumerate()
+            .map(|(i, domain)| {
+                let filter = if domain.contains('/') { format!("||{}", domain) } else { format!("||{}^", domain) };
+                Rule::block(i as u32 + 1, &filter)
+            })
+            .collect()
+    }
+    
+    /// A malformed entry, such as one with `|`, keeps the previous rules
+    fn compile(&mut self) {
+        let _ = self.matcher.set_rules(TRACKING_PROTECTION_SOURCE, self.rules());
+    }
+}
+
+/// Source content blocking's rules compile under in a [`RuleMatcher`]
+pub const CONTENT_BLOCKING_SOURCE: &str = "content-blocking";
+
+/// Third-party content the user can block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentCategory {
+    Fonts,
+    Scripts,
+    Frames,
+}
+
+impl ContentCategory {
+    pub const ALL: [Self; 3] = [Self::Fonts, Self::Scripts, Self::Frames];
+    
+    /// Type of the requests in this category
+    pub fn resource_type(&self) -> ResourceType {
+        match self {
+            Self::Fonts => ResourceType::Font,
+            Self::Scripts => ResourceType::Script,
+            Self::Frames => ResourceType::SubFrame,
+        }
+    }
+}
+
+/// Blocking of third-party fonts, scripts and frames by category, with
+/// per-site settings
+#[derive(Debug, Clone, Default)]
+pub struct ContentBlocking {
+    /// Categories blocked on sites without a setting of their own
+    blocked: HashSet<ContentCategory>,
+    /// Per-site settings for a site and its subdomains, the most specific
+    /// winning; `false` lets the site load the category from third parties
+    sites: HashMap<String, HashMap<ContentCategory, bool>>,
+    matcher: RuleMatcher,
+}
+
+impl ContentBlocking {
+    pub fn new() -> Self { Self::default() }
+    
+    /// Block or allow a category's third-party content by default
+    pub fn set_blocked(&mut self, category: ContentCategory, blocked: bool) {
+        if blocked {
+            self.blocked.insert(category);
+        } else {
+            self.blocked.remove(&category);
+        }
+        self.compile();
+    }
+    
+    /// Whether a category is blocked by default
+    pub fn is_blocked(&self, category: ContentCategory) -> bool {
+        self.blocked.contains(&category)
+    }
+    
+    /// Block or allow a category on a site and its subdomains, whatever the
+    /// default
+    pub fn set_site(&mut self, site: &str, category: ContentCategory, blocked: bool) {
+        self.sites.entry(site.to_lowercase()).or_default().insert(category, blocked);
+        self.compile();
+    }
+    
+    /// Return a site to the defaults
+    pub fn clear_site(&mut self, site: &str) {
+        self.sites.remove(&site.to_lowercase());
+        self.compile();
+    }
+    
+    /// Whether pages at `url` have the category's third-party content blocked
+    pub fn blocks(&self, url: &str, category: ContentCategory) -> bool {
+        let host = host_of(url);
+        self.sites.iter()
+            .filter(|(site, settings)| settings.contains_key(&category) && in_domain(&host, site))
+            .max_by_key(|(site, _)| site.len())
+            .map_or_else(|| self.is_blocked(category), |(_, settings)| settings[&category])
+    }
+    
+    /// One-click unblock: let the site of a page load the category from
+    /// third parties, returning the site. The page must reload for its
+    /// blocked content to load.
+    pub fn unblock(&mut self, page_url: &str, category: ContentCategory) -> Option<String> {
+        let site = host_of(&site_of(page_url)?);
+        self.set_site(&site, category, false);
+        Some(site)
+    }
+    
+    /// Whether a page at `page_url` may not load `url` of a category
+    pub fn should_block(&self, url: &str, page_url: &str, category: ContentCategory) -> bool {
+        let request = RequestDetails::new(url, category.resource_type()).with_initiator(page_url);
+        self.matcher.evaluate(&request).outcome == RuleOutcome::Block
+    }
+    
+    /// The settings as request rules: a block rule per blocked category,
+    /// and a block or allow rule per site setting, of higher priority the
+    /// more specific the site
+    pub fn rules(&self) -> Vec<Rule> {
+        let third_party = |category: ContentCategory, initiator_domains: Vec<String>| RuleCondition {
+            initiator_domains,
+            resource_types: vec![category.resource_type()],
+            domain_type: Some(DomainType::ThirdParty),
+            ..Default::default()
+        };
+        let mut rules: Vec<Rule> = self.blocked.iter()
+            .map(|&category| Rule::new(0, RuleAction::Block, third_party(category, Vec::new())))
+            .collect();
+        for (site, settings) in &self.sites {
+            let priority = site.split('.').count() as u32 + 1;
+            for (&category, &blocked) in settings {
+                let action = if blocked { RuleAction::Block } else { RuleAction::Allow };
+                rules.push(Rule::new(0, action, third_party(category, vec![site.clone()])).with_priority(priority));
+            }
+        }
+        for (i, rule) in rules.iter_mut().enumerate() {
+            rule.id = i as u32 + 1;
+        }
+        rules
+    }
+    
+    fn compile(&mut self) {
+        let _ = self.matcher.set_rules(CONTENT_BLOCKING_SOURCE, self.rules());
+    }
+}
+
+/// Most logical processors `navigator.hardwareConcurrency` reports
+pub const MAX_HARDWARE_CONCURRENCY: u32 = 16;
+/// `navigator.hardwareConcurrency` when resisting fingerprinting
+pub const RESISTED_HARDWARE_CONCURRENCY: u32 = 4;
+/// `navigator.deviceMemory` in GiB when resisting fingerprinting
+pub const RESISTED_DEVICE_MEMORY: f64 = 8.0;
+/// Time zone pages see when resisting fingerprinting
+pub const RESISTED_TIME_ZONE: &str = "UTC";
+/// Font families pages can detect when resisting fingerprinting
+pub const STANDARD_FONT_FAMILIES: &[&str] = &[
+    "serif", "sans-serif", "monospace", "cursive", "fantasy", "system-ui",
+    "Arial", "Courier New", "Georgia", "Times New Roman", "Verdana",
+];
+
+/// `navigator.hardwareConcurrency` for a processor count, at least one and
+/// at most [`MAX_HARDWARE_CONCURRENCY`]
+pub fn clamp_hardware_concurrency(processors: usize) -> u32 {
+    (processors.min(MAX_HARDWARE_CONCURRENCY as usize) as u32).max(1)
+}
+
+/// `navigator.deviceMemory` for an amount of RAM: GiB rounded down to a
+/// power of two, between 0.25 and 8
+pub fn clamp_device_memory(bytes: u64) -> f64 {
+    let gib = bytes as f64 / (1u64 << 30) as f64;
+    if gib < 0.25 {
+        return 0.25;
+    }
+    2f64.powi(gib.log2().floor() as i32).min(8.0)
+}
+
+/// Fingerprinting resistance
+///
+/// Coarsens what pages can learn about the device: hardware concurrency
+/// and memory report common values, the time zone is UTC, font enumeration
+/// sees only standard families and canvas readback gets faint noise. On
+/// for every site when enabled, and toggleable per site either way.
+#[derive(Debug, Clone)]
+pub struct FingerprintingProtection {
+    pub enabled: bool,
+    /// Per-site settings, overriding `enabled` for the site and its
+    /// subdomains
+    sites: HashMap<String, bool>,
+    /// Seeds canvas noise; per browsing session, so reads of the same
+    /// canvas agree within a session but not across sessions
+    session_key: u64,
+}
+
+impl Default for FingerprintingProtection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sites: HashMap::new(),
+            session_key: std::collections::hash_map::RandomState::new().build_hasher().finish(),
+        }
+    }
+}
+
+impl FingerprintingProtection {
+    pub fn new() -> Self { Self::default() }
+    
+    /// Use a fixed noise seed
+    pub fn with_session_key(mut self, key: u64) -> Self {
+        self.session_key = key;
+        self
+    }
+    
+    /// Turn resistance on or off for a site and its subdomains
+    pub fn set_site(&mut self, site: &str, enabled: bool) {
+        self.sites.insert(site.to_lowercase(), enabled);
+    }
+    
+    /// Return a site to the global setting
+    pub fn clear_site(&mut self, site: &str) {
+        self.sites.remove(&site.to_lowercase());
+    }
+    
+    /// Whether a page at `url` gets coarsened values; the most specific
+    /// site setting wins
+    pub fn applies_to(&self, url: &str) -> bool {
+        let host = host_of(url);
+        self.sites.iter()
+            .filter(|(site, _)| in_domain(&host, site))
+            .max_by_key(|(site, _)| site.len())
+            .map_or(self.enabled, |(_, &enabled)| enabled)
+    }
+    
+    /// `navigator.hardwareConcurrency` for a page
+    pub fn hardware_concurrency(&self, url: &str, processors: usize) -> u32 {
+        if self.applies_to(url) {
+            RESISTED_HARDWARE_CONCURRENCY
+        } else {
+            clamp_hardware_concurrency(processors)
+        }
+    }
+    
+    /// `navigator.deviceMemory` for a page
+    pub fn device_memory(&self, url: &str, bytes: u64) -> f64 {
+        if self.applies_to(url) {
+            RESISTED_DEVICE_MEMORY
+        } else {
+            clamp_device_memory(bytes)
+        }
+    }
+    
+    /// Time zone a page sees
+    pub fn time_zone<'a>(&self, url: &str, host_zone: &'a str) -> &'a str {
+        if self.applies_to(url) { RESISTED_TIME_ZONE } else { host_zone }
+    }
+    
+    /// Font families a page can enumerate or detect
+    pub fn visible_fonts<'a>(&self, url: &str, families: &'a [String]) -> Vec<&'a str> {
+        let resist = self.applies_to(url);
+        families.iter()
+            .filter(|family| !resist || STANDARD_FONT_FAMILIES.iter().any(|f| f.eq_ignore_ascii_case(family)))
+            .map(String::as_str)
+            .collect()
+    }
+    
+    /// Add noise to RGBA pixels read back from a canvas
+    ///
+    /// Flips the low bit of one color channel in some pixels. The noise
+    /// depends on the site, session and pixel position only, so averaging
+    /// repeated reads cannot remove it. Transparent pixels stay untouched.
+    pub fn add_canvas_noise(&self, url: &str, rgba: &mut [u8]) {
+        if !self.applies_to(url) {
+            return;
+        }
+        let mut site = std::collections::hash_map::DefaultHasher::new();
+        self.session_key.hash(&mut site);
+        host_of(url).hash(&mut site);
+        let site = site.finish();
+        for (i, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+            if pixel[3] == 0 {
+                continue;
+            }
+            // splitmix64 of the pixel index
+            let mut z = site.wrapping_add((i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            if z & 7 == 0 {
+                pixel[(z >> 8) as usize % 3] ^= 1;
+            }
+        }
+    }
+}
+
+/// Bounce tracking mitigation settings
+#[derive(Debug, Clone)]
+pub struct BounceTrackingConfig {
+    /// Longest a page may stay before moving on, without the user touching
+    /// it, and still count as a bounce
+    pub bounce_dwell: Duration,
+    /// How long a bounce tracker keeps its storage; a user interaction with
+    /// the site in the meantime spares it
+    pub grace_period: Duration,
+    /// Sites the user interacted with this recently are never bounce
+    /// trackers
+    pub interaction_window: Duration,
+}
+
+impl Default for BounceTrackingConfig {
+    fn default() -> Self {
+        Self {
+            bounce_dwell: Duration::from_secs(10),
+            grace_period: Duration::from_secs(60 * 60),
+            interaction_window: Duration::from_secs(45 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// A page on a navigation's redirect chain
+#[derive(Debug, Clone)]
+struct ChainHop {
+    site: String,
+    arrived: Instant,
+    /// Reached by an HTTP redirect, so no document ever showed
+    server_redirect: bool,
+    wrote_storage: bool,
+    interacted: bool,
+}
+
+/// Counters for the tracking protection dashboard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BounceTrackingStats {
+    /// Redirect chains that ended and were checked
+    pub chains_analyzed: usize,
+    /// Sites found bouncing the user while setting storage
+    pub bounces_detected: usize,
+    /// Sites whose storage was purged
+    pub sites_purged: usize,
+    /// Sites waiting out the grace period
+    pub pending_purge: usize,
+}
+
+/// Bounce tracking mitigation
+///
+/// A bounce tracker sits in the middle of a redirect chain: the user
+/// clicks a link on one site, is sent through the tracker, which sets a
+/// first-party cookie in passing, and lands on another site. Each tab's
+/// chain runs from a user-initiated navigation through the server and
+/// client redirects that follow it. When the chain ends, the cross-site
+/// hops that stayed briefly, wrote storage and saw no interaction are
+/// marked, and their storage is purged after a grace period.
+#[derive(Debug, Default)]
+pub struct BounceTracking {
+    config: BounceTrackingConfig,
+    /// Current redirect chain of each tab
+    chains: HashMap<u64, Vec<ChainHop>>,
+    /// Marked sites and when they were marked
+    candidates: HashMap<String, Instant>,
+    /// Last user interaction with each site
+    interactions: HashMap<String, Instant>,
+    stats: BounceTrackingStats,
+}
+
+impl BounceTracking {
+    pub fn new() -> Self { Self::default() }
+    
+    /// Use other thresholds
+    pub fn with_config(mut self, config: BounceTrackingConfig) -> Self {
+        self.config = config;
+        self
+    }
+    
+    /// A tab committed a navigation to `url`; a user-initiated one ends the
+    /// tab's chain and starts the next, any other is a client redirect
+    pub fn navigate(&mut self, tab: u64, url: &str, user_initiated: bool) -> Vec<String> {
+        let detected = if user_initiated { self.end_chain(tab) } else { Vec::new() };
+        self.push_hop(tab, url, false);
+        detected
+    }
+    
+    /// An HTTP redirect sent the tab's navigation to `url`
+    pub fn redirect(&mut self, tab: u64, url: &str) {
+        self.push_hop(tab, url, true);
+    }
+    
+    fn push_hop(&mut self, tab: u64, url: &str, server_redirect: bool) {
+        let Some(site) = site_of(url) else { return };
+        self.chains.entry(tab).or_default().push(ChainHop {
+            site,
+            arrived: Instant::now(),
+            server_redirect,
+            wrote_storage: false,
+            interacted: false,
+        });
+    }
+    
+    /// A page at `url` in the tab set cookies or wrote other storage
+    pub fn storage_written(&mut self, tab: u64, url: &str) {
+        if let Some(hop) = self.current_hop(tab, url) {
+            hop.wrote_storage = true;
+        }
+    }
+    
+    /// The user interacted with a page at `url`, which clears its site of
+    /// suspicion
+    pub fn interaction(&mut self, tab: u64, url: &str) {
+        let Some(site) = site_of(url) else { return };
+        if let Some(hop) = self.current_hop(tab, url) {
+            hop.interacted = true;
+        }
+        self.candidates.remove(&site);
+        self.interactions.insert(site, Instant::now());
+    }
+    
+    fn current_hop(&mut self, tab: u64, url: &str) -> Option<&mut ChainHop> {
+        let site = site_of(url)?;
+        self.chains.get_mut(&tab)?.last_mut().filter(|hop| hop.site == site)
+    }
+    
+    /// Check the tab's chain and forget it, e.g. when the tab closes;
+    /// returns the sites newly marked as bounce trackers
+    pub fn end_chain(&mut self, tab: u64) -> Vec<String> {
+        let Some(chain) = self.chains.remove(&tab) else { return Vec::new() };
+        if chain.len() < 3 {
+            return Vec::new();
+        }
+        self.stats.chains_analyzed += 1;
+        
+        let (first, last) = (&chain[0].site, &chain[chain.len() - 1].site);
+        let mut detected = Vec::new();
+        for pair in chain[1..].windows(2) {
+            let (hop, next) = (&pair[0], &pair[1]);
+            let brief = hop.server_redirect || next.arrived.duration_since(hop.arrived) < self.config.bounce_dwell;
+            let recently_used = self.interactions.get(&hop.site)
+                .is_some_and(|at| at.elapsed() < self.config.interaction_window);
+            if hop.site == *first || hop.site == *last || !hop.wrote_storage || hop.interacted || !brief || recently_used {
+                continue;
+            }
+            if !self.candidates.contains_key(&hop.site) {
+                self.candidates.insert(hop.site.clone(), Instant::now());
+                detected.push(hop.site.clone());
+            }
+        }
+        self.stats.bounces_detected += detected.len();
+        detected
+    }
+    
+    /// Sites marked longer ago than the grace period; the caller clears
+    /// their cookies and storage
+    pub fn purge(&mut self) -> Vec<String> {
+        let grace = self.config.grace_period;
+        let due: Vec<String> = self.candidates.iter()
+            .filter(|(_, marked)| marked.elapsed() >= grace)
+            .map(|(site, _)| site.clone())
+            .collect();
+        for site in &due {
+            self.candidates.remove(site);
+        }
+        self.stats.sites_purged += due.len();
+        
+        let window = self.config.interaction_window;
+        self.interactions.retain(|_, at| at.elapsed() < window);
+        due
+    }
+    
+    /// Whether `url` belongs to a site marked as a bounce tracker
+    pub fn is_marked(&self, url: &str) -> bool {
+        site_of(url).is_some_and(|site| self.candidates.contains_key(&site))
+    }
+    
+    pub fn stats(&self) -> BounceTrackingStats {
+        BounceTrackingStats { pending_purge: self.candidates.len(), ..self.stats }
     }
 }
 
@@ -155,6 +641,77 @@ impl Default for DoNotTrack {
     fn default() -> Self { Self::NotSet }
 }
 
+impl DoNotTrack {
+    /// Value of the `DNT` header; `None` sends no header
+    pub fn header_value(&self) -> Option<&'static str> {
+        match self {
+            Self::NotSet => None,
+            Self::Enabled => Some("1"),
+            Self::Disabled => Some("0"),
+        }
+    }
+}
+
+/// Privacy signals: Global Privacy Control and Do Not Track
+///
+/// `Sec-GPC: 1` asks sites not to sell or share the user's data; pages see
+/// the same preference as `navigator.globalPrivacyControl`. Both signals
+/// are browser-wide preferences a site and its subdomains can be exempted
+/// from, for instance when a site the user subscribes to breaks with them.
+#[derive(Debug, Clone, Default)]
+pub struct PrivacySignals {
+    pub global_privacy_control: bool,
+    pub do_not_track: DoNotTrack,
+    /// Per-site settings; `false` exempts the site and its subdomains, the
+    /// most specific setting winning
+    sites: HashMap<String, bool>,
+}
+
+impl PrivacySignals {
+    pub fn new() -> Self { Self::default() }
+    
+    /// Send or withhold the signals for a site and its subdomains
+    pub fn set_site(&mut self, site: &str, enabled: bool) {
+        self.sites.insert(site.to_lowercase(), enabled);
+    }
+    
+    /// Return a site to the browser-wide preferences
+    pub fn clear_site(&mut self, site: &str) {
+        self.sites.remove(&site.to_lowercase());
+    }
+    
+    /// Whether the signals go to the site of `url`
+    pub fn applies_to(&self, url: &str) -> bool {
+        let host = host_of(url);
+        self.sites.iter()
+            .filter(|(site, _)| in_domain(&host, site))
+            .max_by_key(|(site, _)| site.len())
+            .is_none_or(|(_, &enabled)| enabled)
+    }
+    
+    /// `navigator.globalPrivacyControl` for a page at `url`
+    pub fn global_privacy_control(&self, url: &str) -> bool {
+        self.global_privacy_control && self.applies_to(url)
+    }
+    
+    /// Do Not Track preference sent with requests from a page at `url`
+    pub fn do_not_track(&self, url: &str) -> DoNotTrack {
+        if self.applies_to(url) { self.do_not_track } else { DoNotTrack::NotSet }
+    }
+    
+    /// Headers for requests made on behalf of a page at `url`
+    pub fn headers(&self, url: &str) -> Vec<(&'static str, &'static str)> {
+        let mut headers = Vec::new();
+        if self.global_privacy_control(url) {
+            headers.push(("Sec-GPC", "1"));
+        }
+        if let Some(value) = self.do_not_track(url).header_value() {
+            headers.push(("DNT", value));
+        }
+        headers
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,5 +740,124 @@ mod tests {
         
         assert!(tp.should_block("https://www.doubleclick.net/ads.js"));
         assert!(!tp.should_block("https://example.com/page"));
+        assert!(tp.should_block("https://facebook.com/tr?id=1"));
+        assert!(!tp.should_block("https://notdoubleclick.net/"));
+    }
+    
+    #[test]
+    fn test_content_blocking() {
+        let mut blocking = ContentBlocking::new();
+        blocking.set_blocked(ContentCategory::Fonts, true);
+        blocking.set_blocked(ContentCategory::Frames, true);
+        let page = "https://www.news.com/story";
+        
+        assert!(blocking.should_block("https://fonts.cdn.net/a.woff2", page, ContentCategory::Fonts));
+        assert!(!blocking.should_block("https://static.news.com/a.woff2", page, ContentCategory::Fonts));
+        assert!(!blocking.should_block("https://cdn.net/app.js", page, ContentCategory::Scripts));
+        assert!(blocking.blocks(page, ContentCategory::Frames));
+        
+        // Unblocking frames on one site leaves fonts and other sites alone
+        assert_eq!(blocking.unblock(page, ContentCategory::Frames).as_deref(), Some("news.com"));
+        assert!(!blocking.blocks(page, ContentCategory::Frames));
+        assert!(!blocking.should_block("https://video.io/embed", page, ContentCategory::Frames));
+        assert!(blocking.should_block("https://video.io/embed", "https://blog.org/", ContentCategory::Frames));
+        assert!(blocking.should_block("https://fonts.cdn.net/a.woff2", page, ContentCategory::Fonts));
+        
+        // The most specific site setting wins
+        blocking.set_site("live.news.com", ContentCategory::Frames, true);
+        assert!(blocking.should_block("https://video.io/embed", "https://live.news.com/", ContentCategory::Frames));
+        assert!(blocking.blocks("https://live.news.com/", ContentCategory::Frames));
+        blocking.set_site("blog.org", ContentCategory::Scripts, true);
+        assert!(blocking.should_block("https://cdn.net/app.js", "https://blog.org/", ContentCategory::Scripts));
+        
+        blocking.clear_site("news.com");
+        assert!(blocking.should_block("https://video.io/embed", page, ContentCategory::Frames));
+    }
+    
+    #[test]
+    fn test_fingerprinting_protection() {
+        assert_eq!(clamp_hardware_concurrency(0), 1);
+        assert_eq!(clamp_hardware_concurrency(64), MAX_HARDWARE_CONCURRENCY);
+        assert_eq!(clamp_device_memory(3 << 30), 2.0);
+        assert_eq!(clamp_device_memory(64 << 30), 8.0);
+        assert_eq!(clamp_device_memory(100 << 20), 0.25);
+        
+        let mut fp = FingerprintingProtection::new().with_session_key(7);
+        assert_eq!(fp.hardware_concurrency("https://example.com/", 12), 12);
+        fp.enabled = true;
+        fp.set_site("example.com", false);
+        fp.set_site("app.example.com", true);
+        assert!(fp.applies_to("https://other.org/"));
+        assert!(!fp.applies_to("https://www.example.com/"));
+        assert!(fp.applies_to("https://app.example.com/"));
+        assert_eq!(fp.hardware_concurrency("https://other.org/", 12), RESISTED_HARDWARE_CONCURRENCY);
+        assert_eq!(fp.device_memory("https://other.org/", 32 << 30), RESISTED_DEVICE_MEMORY);
+        assert_eq!(fp.time_zone("https://other.org/", "Europe/Madrid"), "UTC");
+        assert_eq!(fp.time_zone("https://example.com/", "Europe/Madrid"), "Europe/Madrid");
+        
+        let fonts = vec!["Arial".to_string(), "Fira Code".to_string(), "monospace".to_string()];
+        assert_eq!(fp.visible_fonts("https://other.org/", &fonts), vec!["Arial", "monospace"]);
+        assert_eq!(fp.visible_fonts("https://example.com/", &fonts).len(), 3);
+        
+        let original: Vec<u8> = (0..4096u32).map(|i| if i % 4 == 3 { 255 } else { (i % 251) as u8 }).collect();
+        let mut first = original.clone();
+        fp.add_canvas_noise("https://other.org/", &mut first);
+        let mut second = original.clone();
+        fp.add_canvas_noise("https://other.org/", &mut second);
+        assert_eq!(first, second);
+        assert_ne!(first, original);
+        assert!(first.iter().zip(&original).all(|(a, b)| a.abs_diff(*b) <= 1));
+        let mut untouched = original.clone();
+        fp.add_canvas_noise("https://example.com/", &mut untouched);
+        assert_eq!(untouched, original);
+    }
+    
+    #[test]
+    fn test_privacy_signals() {
+        let mut signals = PrivacySignals::new();
+        assert!(signals.headers("https://example.com/").is_empty());
+        
+        signals.global_privacy_control = true;
+        signals.do_not_track = DoNotTrack::Enabled;
+        assert_eq!(signals.headers("https://example.com/"), vec![("Sec-GPC", "1"), ("DNT", "1")]);
+        
+        // A site can be exempted, and a subdomain brought back
+        signals.set_site("news.com", false);
+        signals.set_site("eu.news.com", true);
+        assert!(signals.headers("https://www.news.com/").is_empty());
+        assert!(!signals.global_privacy_control("https://news.com/"));
+        assert!(signals.global_privacy_control("https://eu.news.com/"));
+        signals.clear_site("news.com");
+        assert_eq!(signals.do_not_track("https://news.com/"), DoNotTrack::Enabled);
+    }
+    
+    #[test]
+    fn test_bounce_tracking() {
+        let mut bt = BounceTracking::new().with_config(BounceTrackingConfig {
+            grace_period: Duration::ZERO,
+            ..BounceTrackingConfig::default()
+        });
+        
+        // news -> tracker (sets a cookie) -> shop, without stopping
+        bt.navigate(1, "https://news.com/story", true);
+        bt.redirect(1, "https://tracker.net/r?to=shop");
+        bt.storage_written(1, "https://tracker.net/r?to=shop");
+        bt.navigate(1, "https://www.cdn-hop.io/", false);
+        bt.navigate(1, "https://shop.com/item", false);
+        assert_eq!(bt.navigate(1, "https://other.org/", true), vec!["https://tracker.net"]);
+        assert!(bt.is_marked("https://tracker.net/"));
+        
+        // A site the user interacted with is spared
+        bt.navigate(2, "https://a.com/", true);
+        bt.redirect(2, "https://login.com/sso");
+        bt.storage_written(2, "https://login.com/sso");
+        bt.interaction(2, "https://login.com/sso");
+        bt.navigate(2, "https://b.com/", false);
+        assert!(bt.end_chain(2).is_empty());
+        
+        assert_eq!(bt.stats().pending_purge, 1);
+        assert_eq!(bt.purge(), vec!["https://tracker.net"]);
+        let stats = bt.stats();
+        assert_eq!((stats.chains_analyzed, stats.bounces_detected, stats.sites_purged, stats.pending_purge), (2, 1, 1, 0));
     }
 }
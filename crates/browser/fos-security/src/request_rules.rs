@@ -0,0 +1,648 @@
+//! Request Rules
+//!
+//! Declarative rules that block, allow, redirect or rewrite the headers of
+//! requests, in the form of `declarativeNetRequest`. Tracking protection
+//! and extension rulesets compile into one [`RuleMatcher`], each under its
+//! own source.
+//!
+//! Within a source the matching rule of highest priority decides; at equal
+//! priority allow beats block, which beats redirect. Header rules apply
+//! unless an allow rule of at least their priority matched. Across sources
+//! a block anywhere wins, then the redirect of the source added last.
+
+use crate::partitioning::is_same_site;
+use std::collections::HashMap;
+
+/// Kind of resource a request loads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceType {
+    MainFrame,
+    SubFrame,
+    Stylesheet,
+    Script,
+    Image,
+    Font,
+    XmlHttpRequest,
+    Media,
+    WebSocket,
+    Other,
+}
+
+impl ResourceType {
+    /// Parse a `declarativeNetRequest` resource type name
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "main_frame" => Self::MainFrame,
+            "sub_frame" => Self::SubFrame,
+            "stylesheet" => Self::Stylesheet,
+            "script" => Self::Script,
+            "image" => Self::Image,
+            "font" => Self::Font,
+            "xmlhttprequest" => Self::XmlHttpRequest,
+            "media" => Self::Media,
+            "websocket" => Self::WebSocket,
+            "other" => Self::Other,
+            _ => return None,
+        })
+    }
+}
+
+/// Whether a request goes to the site of its initiator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DomainType {
+    FirstParty,
+    ThirdParty,
+}
+
+impl DomainType {
+    /// Parse a `declarativeNetRequest` domain type name
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "firstParty" => Some(Self::FirstParty),
+            "thirdParty" => Some(Self::ThirdParty),
+            _ => None,
+        }
+    }
+
+    /// Requests without an initiator are first party
+    fn matches(self, request: &RequestDetails) -> bool {
+        let third_party = request.initiator.is_some_and(|initiator| !is_same_site(request.url, initiator));
+        third_party == (self == Self::ThirdParty)
+    }
+}
+
+/// How a header rule changes one header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderOp {
+    Set(String),
+    Append(String),
+    Remove,
+}
+
+/// A header change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderOperation {
+    /// Header name, lowercase
+    pub header: String,
+    pub op: HeaderOp,
+}
+
+impl HeaderOperation {
+    pub fn new(header: &str, op: HeaderOp) -> Self {
+        Self { header: header.to_ascii_lowercase(), op }
+    }
+
+    /// Apply operations in precedence order. Once a header has been set or
+    /// removed, later operations may only append to it.
+    pub fn apply_all(operations: &[HeaderOperation], headers: &mut Vec<(String, String)>) {
+        let mut replaced: Vec<&str> = Vec::new();
+        for operation in operations {
+            let name = operation.header.as_str();
+            match &operation.op {
+                HeaderOp::Append(value) => headers.push((name.to_string(), value.clone())),
+                _ if replaced.contains(&name) => {}
+                op => {
+                    headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+                    if let HeaderOp::Set(value) = op {
+                        headers.push((name.to_string(), value.clone()));
+                    }
+                    replaced.push(name);
+                }
+            }
+        }
+    }
+}
+
+/// What a rule does to the requests it matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleAction {
+    Block,
+    /// Exempt the request from this source's lower priority rules
+    Allow,
+    Redirect(String),
+    ModifyHeaders {
+        request_headers: Vec<HeaderOperation>,
+        response_headers: Vec<HeaderOperation>,
+    },
+}
+
+impl RuleAction {
+    /// Tie-break between rules of equal priority
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Allow => 3,
+            Self::Block => 2,
+            Self::Redirect(_) => 1,
+            Self::ModifyHeaders { .. } => 0,
+        }
+    }
+}
+
+/// Requests a rule applies to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleCondition {
+    /// Filter in the `urlFilter` syntax: `||` anchors at a domain, `|` at
+    /// the start or end of the URL, `*` matches anything and `^` a
+    /// separator. None matches every URL.
+    pub url_filter: Option<String>,
+    /// Hosts, with their subdomains, the request may go to; empty for any
+    pub request_domains: Vec<String>,
+    pub excluded_request_domains: Vec<String>,
+    /// Hosts, with their subdomains, the request may come from; empty for
+    /// any
+    pub initiator_domains: Vec<String>,
+    pub excluded_initiator_domains: Vec<String>,
+    /// Empty for every type but `main_frame`
+    pub resource_types: Vec<ResourceType>,
+    pub excluded_resource_types: Vec<ResourceType>,
+    /// None for requests to any site
+    pub domain_type: Option<DomainType>,
+}
+
+/// A request rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    /// Unique within its source
+    pub id: u32,
+    /// At least 1
+    pub priority: u32,
+    pub condition: RuleCondition,
+    pub action: RuleAction,
+}
+
+impl Rule {
+    pub fn new(id: u32, action: RuleAction, condition: RuleCondition) -> Self {
+        Self { id, priority: 1, condition, action }
+    }
+
+    /// Block requests matching a URL filter
+    pub fn block(id: u32, url_filter: &str) -> Self {
+        let condition = RuleCondition { url_filter: Some(url_filter.to_string()), ..Default::default() };
+        Self::new(id, RuleAction::Block, condition)
+    }
+
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+/// The request being matched
+#[derive(Debug, Clone, Copy)]
+pub struct RequestDetails<'a> {
+    pub url: &'a str,
+    /// URL of the document or worker making the request
+    pub initiator: Option<&'a str>,
+    pub resource_type: ResourceType,
+}
+
+impl<'a> RequestDetails<'a> {
+    pub fn new(url: &'a str, resource_type: ResourceType) -> Self {
+        Self { url, initiator: None, resource_type }
+    }
+
+    pub fn with_initiator(mut self, initiator: &'a str) -> Self {
+        self.initiator = Some(initiator);
+        self
+    }
+}
+
+/// What happens to a request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RuleOutcome {
+    #[default]
+    Continue,
+    Block,
+    Redirect(String),
+}
+
+/// The rules' verdict on a request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleVerdict {
+    pub outcome: RuleOutcome,
+    /// In precedence order, for [`HeaderOperation::apply_all`]
+    pub request_headers: Vec<HeaderOperation>,
+    pub response_headers: Vec<HeaderOperation>,
+}
+
+/// Why rules were refused
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RuleError {
+    #[error("Rule {0}: duplicate rule id")]
+    DuplicateId(u32),
+
+    #[error("Rule {0}: priority must be at least 1")]
+    InvalidPriority(u32),
+
+    #[error("Rule {0}: invalid urlFilter \"{1}\"")]
+    InvalidUrlFilter(u32, String),
+
+    #[error("Rule {0}: invalid redirect URL \"{1}\"")]
+    InvalidRedirect(u32, String),
+
+    #[error("Rule {0}: modifyHeaders needs at least one header operation")]
+    NoHeaderOperations(u32),
+}
+
+/// Piece of a compiled URL filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Wildcard,
+    Separator,
+}
+
+/// Where a URL filter must start matching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Anchor {
+    None,
+    Start,
+    Domain,
+}
+
+/// A compiled `urlFilter`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UrlFilter {
+    anchor: Anchor,
+    pieces: Vec<Piece>,
+    end_anchor: bool,
+}
+
+impl UrlFilter {
+    fn parse(filter: &str) -> Option<Self> {
+        if filter.is_empty() || !filter.is_ascii() {
+            return None;
+        }
+        let filter = filter.to_ascii_lowercase();
+        let (anchor, rest) = if let Some(rest) = filter.strip_prefix("||") {
+            (Anchor::Domain, rest)
+        } else if let Some(rest) = filter.strip_prefix('|') {
+            (Anchor::Start, rest)
+        } else {
+            (Anchor::None, filter.as_str())
+        };
+        let (rest, end_anchor) = match rest.strip_suffix('|') {
+            Some(rest) => (rest, true),
+            None => (rest, false),
+        };
+        if rest.contains('|') {
+            return None;
+        }
+
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        for c in rest.chars() {
+            let piece = match c {
+                '*' => Piece::Wildcard,
+                '^' => Piece::Separator,
+                _ => {
+                    literal.push(c);
+                    continue;
+                }
+            };
+            if !literal.is_empty() {
+                pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+            }
+            pieces.push(piece);
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Some(Self { anchor, pieces, end_anchor })
+    }
+
+    /// Domain a `||` filter names in full, to index it by
+    fn domain(&self) -> Option<&str> {
+        if self.anchor != Anchor::Domain {
+            return None;
+        }
+        let Some(Piece::Literal(literal)) = self.pieces.first() else { return None };
+        let domain = literal.split('/').next().unwrap_or_default();
+        // `||ads*` or `||example.com:8080` name no whole domain
+        let whole = match self.pieces.get(1) {
+            Some(Piece::Wildcard) => domain.len() < literal.len(),
+            _ => true,
+        };
+        (whole && !domain.is_empty() && !domain.contains(':')).then_some(domain)
+    }
+
+    /// Match against a lowercase URL
+    fn matches(&self, url: &str) -> bool {
+        let url = url.as_bytes();
+        match self.anchor {
+            Anchor::Start => self.match_at(url, 0, 0),
+            Anchor::None => (0..=url.len()).any(|pos| self.match_at(url, 0, pos)),
+            Anchor::Domain => {
+                let (start, end) = host_range(url);
+                // The host, or after a dot within it
+                std::iter::once(start)
+                    .chain((start..end).filter(|&i| url[i] == b'.').map(|i| i + 1))
+                    .any(|pos| self.match_at(url, 0, pos))
+            }
+        }
+    }
+
+    fn match_at(&self, url: &[u8], piece: usize, pos: usize) -> bool {
+        let Some(current) = self.pieces.get(piece) else {
+            return !self.end_anchor || pos == url.len();
+        };
+        match current {
+            Piece::Literal(literal) => {
+                url[pos..].starts_with(literal.as_bytes()) && self.match_at(url, piece + 1, pos + literal.len())
+            }
+            Piece::Separator => match url.get(pos) {
+                None => self.match_at(url, piece + 1, pos),
+                Some(&c) => is_separator(c) && self.match_at(url, piece + 1, pos + 1),
+            },
+            Piece::Wildcard => (pos..=url.len()).any(|p| self.match_at(url, piece + 1, p)),
+        }
+    }
+}
+
+/// `^` matches anything but a letter, digit or one of `_-.%`
+fn is_separator(c: u8) -> bool {
+    !(c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b'.' | b'%'))
+}
+
+/// Byte range of a URL's host
+fn host_range(url: &[u8]) -> (usize, usize) {
+    let start = url.windows(3).position(|w| w == b"://").map_or(0, |i| i + 3);
+    let end = url[start..].iter()
+        .position(|&c| matches!(c, b'/' | b'?' | b'#' | b':'))
+        .map_or(url.len(), |i| start + i);
+    // Skip credentials
+    let start = url[start..end].iter().rposition(|&c| c == b'@').map_or(start, |i| start + i + 1);
+    (start, end)
+}
+
+/// Host of a URL, lowercase
+pub(crate) fn host_of(url: &str) -> String {
+    let (start, end) = host_range(url.as_bytes());
+    url[start..end].to_ascii_lowercase()
+}
+
+/// Whether a host is `domain` or one of its subdomains
+pub(crate) fn in_domain(host: &str, domain: &str) -> bool {
+    host.strip_suffix(domain).is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+}
+
+/// A rule with its filter compiled
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    rule: Rule,
+    filter: Option<UrlFilter>,
+}
+
+impl CompiledRule {
+    fn compile(mut rule: Rule) -> Result<Self, RuleError> {
+        if rule.priority == 0 {
+            return Err(RuleError::InvalidPriority(rule.id));
+        }
+        let filter = match &rule.condition.url_filter {
+            Some(filter) => Some(UrlFilter::parse(filter).ok_or_else(|| RuleError::InvalidUrlFilter(rule.id, filter.clone()))?),
+            None => None,
+        };
+        match &rule.action {
+            RuleAction::Redirect(url) if !url.contains("://") => {
+                return Err(RuleError::InvalidRedirect(rule.id, url.clone()));
+            }
+            RuleAction::ModifyHeaders { request_headers, response_headers } if request_headers.is_empty() && response_headers.is_empty() => {
+                return Err(RuleError::NoHeaderOperations(rule.id));
+            }
+            _ => {}
+        }
+        let condition = &mut rule.condition;
+        for domains in [
+            &mut condition.request_domains,
+            &mut condition.excluded_request_domains,
+            &mut condition.initiator_domains,
+            &mut condition.excluded_initiator_domains,
+        ] {
+            domains.iter_mut().for_each(|d| d.make_ascii_lowercase());
+        }
+        Ok(Self { rule, filter })
+    }
+
+    fn matches(&self, request: &RequestDetails, url: &str, host: &str, initiator: Option<&str>) -> bool {
+        let condition = &self.rule.condition;
+        let types_match = if condition.resource_types.is_empty() {
+            request.resource_type != ResourceType::MainFrame
+        } else {
+            condition.resource_types.contains(&request.resource_type)
+        };
+        let any = |domains: &[String], host: &str| domains.iter().any(|d| in_domain(host, d));
+        types_match
+            && !condition.excluded_resource_types.contains(&request.resource_type)
+            && (condition.request_domains.is_empty() || any(&condition.request_domains, host))
+            && !any(&condition.excluded_request_domains, host)
+            && (condition.initiator_domains.is_empty() || initiator.is_some_and(|i| any(&condition.initiator_domains, i)))
+            && !initiator.is_some_and(|i| any(&condition.excluded_initiator_domains, i))
+            && self.filter.as_ref().is_none_or(|f| f.matches(url))
+            && condition.domain_type.is_none_or(|t| t.matches(request))
+    }
+}
+
+/// One source's rules, indexed by the domain their filter anchors at
+#[derive(Debug, Clone)]
+struct RuleSet {
+    source: String,
+    rules: Vec<CompiledRule>,
+    by_domain: HashMap<String, Vec<usize>>,
+    /// Rules no domain narrows down
+    generic: Vec<usize>,
+}
+
+impl RuleSet {
+    fn compile(source: &str, rules: Vec<Rule>) -> Result<Self, RuleError> {
+        let mut set = Self { source: source.to_string(), rules: Vec::new(), by_domain: HashMap::new(), generic: Vec::new() };
+        let mut ids = std::collections::HashSet::new();
+        for rule in rules {
+            if !ids.insert(rule.id) {
+                return Err(RuleError::DuplicateId(rule.id));
+            }
+            let compiled = CompiledRule::compile(rule)?;
+            let index = set.rules.len();
+            match compiled.filter.as_ref().and_then(UrlFilter::domain) {
+                Some(domain) => set.by_domain.entry(domain.to_string()).or_default().push(index),
+                None => set.generic.push(index),
+            }
+            set.rules.push(compiled);
+        }
+        Ok(set)
+    }
+
+    /// Matching rules, best first
+    fn matching(&self, request: &RequestDetails, url: &str, host: &str, initiator: Option<&str>) -> Vec<&Rule> {
+        let suffixes = std::iter::once(host).chain(host.match_indices('.').map(|(i, _)| &host[i + 1..]));
+        let candidates = suffixes
+            .filter_map(|suffix| self.by_domain.get(suffix))
+            .flatten()
+            .chain(&self.generic);
+        let mut rules: Vec<&Rule> = candidates
+            .map(|&i| &self.rules[i])
+            .filter(|rule| rule.matches(request, url, host, initiator))
+            .map(|rule| &rule.rule)
+            .collect();
+        rules.sort_by_key(|rule| std::cmp::Reverse((rule.priority, rule.action.rank(), std::cmp::Reverse(rule.id))));
+        rules
+    }
+}
+
+/// Compiled request rules from every source
+#[derive(Debug, Clone, Default)]
+pub struct RuleMatcher {
+    /// In the order sources were added
+    sets: Vec<RuleSet>,
+}
+
+impl RuleMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compile a source's rules, replacing any it had; on error the
+    /// source keeps its old rules
+    pub fn set_rules(&mut self, source: &str, rules: Vec<Rule>) -> Result<(), RuleError> {
+        let set = RuleSet::compile(source, rules)?;
+        self.remove_rules(source);
+        self.sets.push(set);
+        Ok(())
+    }
+
+    /// Drop a source's rules
+    pub fn remove_rules(&mut self, source: &str) -> bool {
+        let before = self.sets.len();
+        self.sets.retain(|set| set.source != source);
+        self.sets.len() != before
+    }
+
+    /// Number of rules a source has
+    pub fn rule_count(&self, source: &str) -> usize {
+        self.sets.iter().find(|set| set.source == source).map_or(0, |set| set.rules.len())
+    }
+
+    /// Verdict of every rule on a request
+    pub fn evaluate(&self, request: &RequestDetails) -> RuleVerdict {
+        self.evaluate_filtered(request, |_, _| true)
+    }
+
+    /// Verdict of the rules `allowed` accepts, given each rule's source,
+    /// e.g. to skip redirects a source lacks host access for
+    pub fn evaluate_filtered(&self, request: &RequestDetails, allowed: impl Fn(&str, &Rule) -> bool) -> RuleVerdict {
+        let url = request.url.to_ascii_lowercase();
+        let host = host_of(request.url);
+        let initiator = request.initiator.map(host_of);
+
+        let mut verdict = RuleVerdict::default();
+        let mut redirect = None;
+        // Latest source first, for redirect and header precedence
+        for set in self.sets.iter().rev() {
+            let rules: Vec<&Rule> = set.matching(request, &url, &host, initiator.as_deref())
+                .into_iter()
+                .filter(|rule| allowed(&set.source, rule))
+                .collect();
+            let decisive = rules.iter().find(|rule| !matches!(rule.action, RuleAction::ModifyHeaders { .. }));
+            match decisive.map(|rule| &rule.action) {
+                Some(RuleAction::Block) => return RuleVerdict { outcome: RuleOutcome::Block, ..Default::default() },
+                Some(RuleAction::Redirect(url)) => {
+                    redirect.get_or_insert_with(|| url.clone());
+                    continue;
+                }
+                _ => {}
+            }
+
+            let allow_priority = decisive.map_or(0, |rule| rule.priority);
+            for rule in rules.iter().filter(|rule| rule.priority > allow_priority) {
+                if let RuleAction::ModifyHeaders { request_headers, response_headers } = &rule.action {
+                    verdict.request_headers.extend(request_headers.iter().cloned());
+                    verdict.response_headers.extend(response_headers.iter().cloned());
+                }
+            }
+        }
+        if let Some(url) = redirect {
+            return RuleVerdict { outcome: RuleOutcome::Redirect(url), ..Default::default() };
+        }
+        verdict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(url: &str) -> RequestDetails<'_> {
+        RequestDetails::new(url, ResourceType::Script)
+    }
+
+    #[test]
+    fn test_url_filters() {
+        let filter = |f: &str, url: &str| UrlFilter::parse(f).unwrap().matches(&url.to_ascii_lowercase());
+        assert!(filter("||ads.com^", "https://ads.com/x.js"));
+        assert!(filter("||ads.com^", "https://cdn.ads.com:8080/"));
+        assert!(!filter("||ads.com^", "https://badads.com/"));
+        assert!(!filter("||ads.com^", "https://ads.com.evil.org/"));
+        assert!(filter("||ads.com/track", "https://www.ADS.com/tracker"));
+        assert!(filter("|https://*.js|", "https://a.com/b.js"));
+        assert!(!filter("|https://*.js|", "https://a.com/b.js?x"));
+        assert!(filter("banner", "https://a.com/img/banner.png"));
+        assert!(filter("/ad^", "https://a.com/ad?x=1"));
+        assert!(!filter("/ad^", "https://a.com/adverts"));
+        assert!(UrlFilter::parse("a|b").is_none());
+
+        assert_eq!(UrlFilter::parse("||ads.com^").unwrap().domain(), Some("ads.com"));
+        assert_eq!(UrlFilter::parse("||ads*").unwrap().domain(), None);
+    }
+
+    #[test]
+    fn test_rule_precedence() {
+        let mut matcher = RuleMatcher::new();
+        let allow_docs = Rule::new(2, RuleAction::Allow, RuleCondition {
+            url_filter: Some("||example.com/docs/".into()),
+            ..Default::default()
+        }).with_priority(2);
+        let headers = Rule::new(3, RuleAction::ModifyHeaders {
+            request_headers: vec![HeaderOperation::new("Cookie", HeaderOp::Remove)],
+            response_headers: vec![],
+        }, RuleCondition { request_domains: vec!["example.com".into()], ..Default::default() }).with_priority(3);
+        matcher.set_rules("ext-a", vec![Rule::block(1, "||example.com^"), allow_docs, headers]).unwrap();
+
+        assert_eq!(matcher.evaluate(&script("https://example.com/app.js")).outcome, RuleOutcome::Block);
+        let verdict = matcher.evaluate(&script("https://www.example.com/docs/a.js"));
+        assert_eq!(verdict.outcome, RuleOutcome::Continue);
+        assert_eq!(verdict.request_headers, vec![HeaderOperation::new("cookie", HeaderOp::Remove)]);
+        // Main frames only match rules that name them
+        assert_eq!(matcher.evaluate(&RequestDetails::new("https://example.com/", ResourceType::MainFrame)).outcome, RuleOutcome::Continue);
+
+        // Another source's allow does not lift ext-a's block
+        let redirect = Rule::new(1, RuleAction::Redirect("https://example.org/".into()), RuleCondition::default());
+        matcher.set_rules("ext-b", vec![redirect, Rule::new(2, RuleAction::Allow, RuleCondition::default())]).unwrap();
+        assert_eq!(matcher.evaluate(&script("https://example.com/app.js")).outcome, RuleOutcome::Block);
+        matcher.remove_rules("ext-a");
+        assert_eq!(matcher.evaluate(&script("https://example.com/app.js")).outcome, RuleOutcome::Continue);
+
+        assert_eq!(matcher.set_rules("ext-c", vec![Rule::block(1, "a"), Rule::block(1, "b")]), Err(RuleError::DuplicateId(1)));
+        assert_eq!(matcher.rule_count("ext-c"), 0);
+
+        matcher.remove_rules("ext-b");
+        let third_party = Rule::new(1, RuleAction::Block, RuleCondition { domain_type: Some(DomainType::ThirdParty), ..Default::default() });
+        matcher.set_rules("ext-c", vec![third_party]).unwrap();
+        assert_eq!(matcher.evaluate(&script("https://cdn.example.net/a.js").with_initiator("https://www.example.net/")).outcome, RuleOutcome::Continue);
+        assert_eq!(matcher.evaluate(&script("https://cdn.other.org/a.js").with_initiator("https://www.example.net/")).outcome, RuleOutcome::Block);
+        assert_eq!(matcher.evaluate(&script("https://cdn.other.org/a.js")).outcome, RuleOutcome::Continue);
+    }
+
+    #[test]
+    fn test_header_operations() {
+        let mut headers = vec![("cookie".to_string(), "a=1".to_string()), ("accept".to_string(), "*/*".to_string())];
+        HeaderOperation::apply_all(&[
+            HeaderOperation::new("Cookie", HeaderOp::Set("b=2".into())),
+            HeaderOperation::new("cookie", HeaderOp::Remove),
+            HeaderOperation::new("x-extra", HeaderOp::Append("1".into())),
+        ], &mut headers);
+        assert_eq!(headers, vec![
+            ("accept".to_string(), "*/*".to_string()),
+            ("cookie".to_string(), "b=2".to_string()),
+            ("x-extra".to_string(), "1".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,261 @@
+//! Storage Access API
+//!
+//! An iframe embedded cross-site gets partitioned storage and, under the
+//! default cookie policy, no cookies. `document.requestStorageAccess()`
+//! lets it ask for its unpartitioned cookies and storage on one top-level
+//! site; `document.hasStorageAccess()` tells it whether it has them.
+//!
+//! Grants are per (embedded site, top-level site) pair and expire. A
+//! request either is granted automatically, when the user recently
+//! interacted with the embedded site as a first party, or prompts the user.
+
+use crate::partitioning::{site_of, StorageKey};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// When requests are granted without asking
+#[derive(Debug, Clone)]
+pub struct StorageAccessConfig {
+    /// Grant automatically if the user visited the embedded site as a first
+    /// party this recently; `None` never grants automatically
+    pub interaction_window: Option<Duration>,
+    /// Most automatic grants one top-level site may collect
+    pub auto_grant_limit: usize,
+    /// Ask the user when a request is not granted automatically; otherwise
+    /// such requests are denied
+    pub prompt: bool,
+    /// How long a grant lasts
+    pub grant_lifetime: Duration,
+}
+
+impl Default for StorageAccessConfig {
+    fn default() -> Self {
+        Self {
+            interaction_window: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            auto_grant_limit: 5,
+            prompt: true,
+            grant_lifetime: Duration::from_secs(30 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// Outcome of `document.requestStorageAccess()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageAccessResult {
+    Granted,
+    Denied,
+    /// The user is being asked; settle it with `resolve_prompt`
+    Prompt,
+}
+
+/// (embedded site, top-level site)
+type SitePair = (String, String);
+
+/// Storage access grants of a browser profile
+#[derive(Debug, Default)]
+pub struct StorageAccess {
+    config: StorageAccessConfig,
+    /// Expiry of each grant
+    grants: HashMap<SitePair, Instant>,
+    /// Pairs the user refused; not asked again
+    denied: HashSet<SitePair>,
+    /// Pairs waiting on a prompt
+    pending: HashSet<SitePair>,
+    /// Last first-party interaction with each site
+    interactions: HashMap<String, Instant>,
+    /// Automatic grants made on each top-level site
+    auto_grants: HashMap<String, usize>,
+}
+
+impl StorageAccess {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use other heuristics
+    pub fn with_config(mut self, config: StorageAccessConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &StorageAccessConfig {
+        &self.config
+    }
+
+    /// Sites of a third-party key; `None` if it needs no grant or can
+    /// never get one
+    fn pair(key: &StorageKey) -> Option<SitePair> {
+        if key.is_opaque() || !key.is_third_party() {
+            return None;
+        }
+        Some((site_of(&key.origin)?, key.top_level_site.clone()))
+    }
+
+    /// `document.hasStorageAccess()`: first parties always have it
+    pub fn has_storage_access(&self, key: &StorageKey) -> bool {
+        if key.is_opaque() {
+            return false;
+        }
+        match Self::pair(key) {
+            None => true,
+            Some(pair) => self.grants.get(&pair).is_some_and(|expiry| *expiry > Instant::now()),
+        }
+    }
+
+    /// `document.requestStorageAccess()`, which needs a user gesture
+    pub fn request_storage_access(&mut self, key: &StorageKey, user_activation: bool) -> StorageAccessResult {
+        if key.is_opaque() {
+            return StorageAccessResult::Denied;
+        }
+        if self.has_storage_access(key) {
+            return StorageAccessResult::Granted;
+        }
+        let Some(pair) = Self::pair(key) else {
+            return StorageAccessResult::Denied;
+        };
+        if !user_activation || self.denied.contains(&pair) {
+            return StorageAccessResult::Denied;
+        }
+
+        let interacted = match (self.config.interaction_window, self.interactions.get(&pair.0)) {
+            (Some(window), Some(at)) => at.elapsed() <= window,
+            _ => false,
+        };
+        let auto_grants = self.auto_grants.get(&pair.1).copied().unwrap_or(0);
+        if interacted && auto_grants < self.config.auto_grant_limit {
+            *self.auto_grants.entry(pair.1.clone()).or_default() += 1;
+            self.grant(pair);
+            return StorageAccessResult::Granted;
+        }
+
+        if self.config.prompt {
+            self.pending.insert(pair);
+            StorageAccessResult::Prompt
+        } else {
+            StorageAccessResult::Denied
+        }
+    }
+
+    /// Requests waiting on the user, as (embedded site, top-level site)
+    pub fn pending_prompts(&self) -> impl Iterator<Item = &(String, String)> {
+        self.pending.iter()
+    }
+
+    /// The user answered the prompt for `key`; false if none was pending
+    pub fn resolve_prompt(&mut self, key: &StorageKey, allow: bool) -> bool {
+        let Some(pair) = Self::pair(key) else {
+            return false;
+        };
+        if !self.pending.remove(&pair) {
+            return false;
+        }
+        if allow {
+            self.grant(pair);
+        } else {
+            self.denied.insert(pair);
+        }
+        true
+    }
+
+    fn grant(&mut self, pair: SitePair) {
+        self.denied.remove(&pair);
+        self.grants.insert(pair, Instant::now() + self.config.grant_lifetime);
+    }
+
+    /// The user interacted with a top-level document at `url`
+    pub fn record_interaction(&mut self, url: &str) {
+        if let Some(site) = site_of(url) {
+            self.interactions.insert(site, Instant::now());
+        }
+    }
+
+    /// Key whose cookies and storage a document with `key` uses: the
+    /// unpartitioned first-party key while it has storage access
+    pub fn effective_key(&self, key: &StorageKey) -> StorageKey {
+        if key.is_third_party() && self.has_storage_access(key) {
+            StorageKey::first_party(&key.origin)
+        } else {
+            key.clone()
+        }
+    }
+
+    /// Drop expired grants
+    pub fn cleanup(&mut self) {
+        let now = Instant::now();
+        self.grants.retain(|_, expiry| *expiry > now);
+    }
+
+    /// Forget grants, refusals and interactions involving `site`
+    pub fn clear_site(&mut self, site: &str) {
+        let involves = |pair: &SitePair| pair.0 == site || pair.1 == site;
+        self.grants.retain(|pair, _| !involves(pair));
+        self.denied.retain(|pair| !involves(pair));
+        self.pending.retain(|pair| !involves(pair));
+        self.interactions.remove(site);
+        self.auto_grants.remove(site);
+    }
+
+    /// Forget everything, e.g. when browsing data is cleared
+    pub fn clear(&mut self) {
+        self.grants.clear();
+        self.denied.clear();
+        self.pending.clear();
+        self.interactions.clear();
+        self.auto_grants.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompted_grant() {
+        let mut access = StorageAccess::new();
+        let key = StorageKey::new("https://news.com/", "https://comments.net/embed");
+        assert!(!access.has_storage_access(&key));
+        assert!(access.has_storage_access(&StorageKey::first_party("https://comments.net/")));
+
+        // Without a user gesture the request fails outright
+        assert_eq!(access.request_storage_access(&key, false), StorageAccessResult::Denied);
+        assert_eq!(access.request_storage_access(&key, true), StorageAccessResult::Prompt);
+        assert!(access.resolve_prompt(&key, true));
+        assert!(access.has_storage_access(&key));
+        assert_eq!(access.effective_key(&key), StorageKey::first_party("https://comments.net/"));
+
+        // The grant covers the pair of sites, not other top-level sites
+        let elsewhere = StorageKey::new("https://blog.org/", "https://comments.net/embed");
+        assert!(!access.has_storage_access(&elsewhere));
+        assert_eq!(access.effective_key(&elsewhere), elsewhere);
+        assert_eq!(access.request_storage_access(&elsewhere, true), StorageAccessResult::Prompt);
+        access.resolve_prompt(&elsewhere, false);
+        assert_eq!(access.request_storage_access(&elsewhere, true), StorageAccessResult::Denied);
+    }
+
+    #[test]
+    fn test_automatic_grants_and_expiry() {
+        let mut access = StorageAccess::new().with_config(StorageAccessConfig {
+            auto_grant_limit: 1,
+            prompt: false,
+            ..StorageAccessConfig::default()
+        });
+        access.record_interaction("https://www.comments.net/login");
+
+        let key = StorageKey::new("https://news.com/", "https://comments.net/embed");
+        assert_eq!(access.request_storage_access(&key, true), StorageAccessResult::Granted);
+        // The top-level site used up its automatic grants
+        let other = StorageKey::new("https://news.com/", "https://www.comments.net/widget");
+        assert!(access.has_storage_access(&other));
+        let video = StorageKey::new("https://news.com/", "https://video.io/");
+        access.record_interaction("https://video.io/");
+        assert_eq!(access.request_storage_access(&video, true), StorageAccessResult::Denied);
+
+        let mut short = StorageAccess::new().with_config(StorageAccessConfig {
+            grant_lifetime: Duration::ZERO,
+            ..StorageAccessConfig::default()
+        });
+        short.request_storage_access(&key, true);
+        short.resolve_prompt(&key, true);
+        assert!(!short.has_storage_access(&key));
+    }
+}
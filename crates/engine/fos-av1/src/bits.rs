@@ -0,0 +1,56 @@
+//! Header Bit Reader
+//!
+//! Most significant bit first reader for OBU headers and the uncompressed
+//! frame header.
+
+use crate::{Av1Error, Av1Result};
+
+#[derive(Debug)]
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    /// Bits consumed so far
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Read `n` bits, at most 32
+    pub fn read_bits(&mut self, n: u32) -> Av1Result<u32> {
+        if n > 32 {
+            return Err(Av1Error::InvalidBitstream(format!("Cannot read {} bits at once", n)));
+        }
+        if self.pos + n as usize > self.data.len() * 8 {
+            return Err(Av1Error::NeedMoreData);
+        }
+        let mut v = 0u64;
+        for _ in 0..n {
+            let bit = (self.data[self.pos / 8] >> (7 - self.pos % 8)) & 1;
+            v = (v << 1) | bit as u64;
+            self.pos += 1;
+        }
+        Ok(v as u32)
+    }
+
+    /// Bits consumed so far
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bit_reader() {
+        let data = [0b1011_0100, 0b1100_1010];
+        let mut r = BitReader::new(&data);
+        assert_eq!(r.read_bits(4).unwrap(), 0b1011);
+        assert_eq!(r.read_bits(12).unwrap(), 0b0100_1100_1010);
+        assert_eq!(r.position(), 16);
+        assert!(matches!(r.read_bits(1), Err(Av1Error::NeedMoreData)));
+    }
+}
@@ -0,0 +1,194 @@
+//! AV1 Constrained Directional Enhancement Filter
+//!
+//! Finds the dominant edge direction of each 8x8 block and smooths along
+//! it, limiting every tap by how far it is from the filtered sample. All
+//! taps read the deblocked frame, never already filtered samples.
+
+use super::state::{Grid, MiInfo, PlaneBuf};
+use super::tables::{CDEF_DIRECTIONS, CDEF_DIV_TABLE, CDEF_UV_DIR};
+use super::tile::FrameContext;
+
+const CDEF_PRI_TAPS: [[i32; 2]; 2] = [[4, 2], [3, 3]];
+const CDEF_SEC_TAPS: [[i32; 2]; 2] = [[2, 1], [2, 1]];
+
+/// Filter every plane of the frame in place
+pub(super) fn cdef_frame(f: &FrameContext, planes: &mut [PlaneBuf], mi: &Grid<MiInfo>, cdef_idx: &Grid<i8>) {
+    let hdr = f.hdr;
+    if !cdef_idx.data.iter().any(|&i| i >= 0) {
+        return;
+    }
+    let source: Vec<PlaneBuf> = planes.to_vec();
+    for r in (0..hdr.mi_rows).step_by(2) {
+        for c in (0..hdr.mi_cols).step_by(2) {
+            let idx = *cdef_idx.get(r >> 4, c >> 4);
+            if idx < 0 {
+                continue;
+            }
+            let skip = mi.get(r, c).skip && mi.get(r + 1, c).skip && mi.get(r, c + 1).skip && mi.get(r + 1, c + 1).skip;
+            if !skip {
+                cdef_block(f, &source, planes, r, c, idx as usize);
+            }
+        }
+    }
+}
+
+fn cdef_block(f: &FrameContext, src: &[PlaneBuf], dst: &mut [PlaneBuf], r: usize, c: usize, idx: usize) {
+    let hdr = f.hdr;
+    let shift = f.bit_depth as i32 - 8;
+    let (y_dir, var) = cdef_direction(&src[0], r, c, shift);
+    let pri = (hdr.cdef_y_pri_strength[idx] as i32) << shift;
+    let sec = (hdr.cdef_y_sec_strength[idx] as i32) << shift;
+    let dir = if pri == 0 { 0 } else { y_dir };
+    let var_str = if var >> 6 != 0 { ((var >> 6).ilog2() as i32).min(12) } else { 0 };
+    let adjusted = if var != 0 { (pri * (4 + var_str) + 8) >> 4 } else { 0 };
+    let damping = hdr.cdef_damping as i32 + shift;
+    cdef_filter(f, &src[0], &mut dst[0], 0, r, c, adjusted, sec, damping, dir);
+    if f.num_planes == 1 {
+        return;
+    }
+    let pri = (hdr.cdef_uv_pri_strength[idx] as i32) << shift;
+    let sec = (hdr.cdef_uv_sec_strength[idx] as i32) << shift;
+    let dir = if pri == 0 { 0 } else { CDEF_UV_DIR[f.ss_x as usize][f.ss_y as usize][y_dir] as usize };
+    for plane in 1..3 {
+        cdef_filter(f, &src[plane], &mut dst[plane], plane, r, c, pri, sec, damping - 1, dir);
+    }
+}
+
+/// Dominant direction of the luma 8x8 block and the variance along it
+fn cdef_direction(src: &PlaneBuf, r: usize, c: usize, shift: i32) -> (usize, i32) {
+    let mut cost = [0i32; 8];
+    let mut partial = [[0i32; 15]; 8];
+    let (x0, y0) = (c * 4, r * 4);
+    for i in 0..8 {
+        for j in 0..8 {
+            let x = (*src.get(y0 + i, x0 + j) as i32 >> shift) - 128;
+            partial[0][i + j] += x;
+            partial[1][i + j / 2] += x;
+            partial[2][i] += x;
+            partial[3][3 + i - j / 2] += x;
+            partial[4][7 + i - j] += x;
+            partial[5][3 - i / 2 + j] += x;
+            partial[6][j] += x;
+            partial[7][i / 2 + j] += x;
+        }
+    }
+    cost[2] = partial[2][..8].iter().map(|v| v * v).sum();
+    cost[6] = partial[6][..8].iter().map(|v| v * v).sum();
+    cost[2] *= CDEF_DIV_TABLE[8];
+    cost[6] *= CDEF_DIV_TABLE[8];
+    for i in 0..7 {
+        cost[0] += (partial[0][i] * partial[0][i] + partial[0][14 - i] * partial[0][14 - i]) * CDEF_DIV_TABLE[i + 1];
+        cost[4] += (partial[4][i] * partial[4][i] + partial[4][14 - i] * partial[4][14 - i]) * CDEF_DIV_TABLE[i + 1];
+    }
+    cost[0] += partial[0][7] * partial[0][7] * CDEF_DIV_TABLE[8];
+    cost[4] += partial[4][7] * partial[4][7] * CDEF_DIV_TABLE[8];
+    for i in (1..8).step_by(2) {
+        for j in 0..5 {
+            cost[i] += partial[i][3 + j] * partial[i][3 + j];
+        }
+        cost[i] *= CDEF_DIV_TABLE[8];
+        for j in 0..3 {
+            cost[i] += (partial[i][j] * partial[i][j] + partial[i][10 - j] * partial[i][10 - j]) * CDEF_DIV_TABLE[2 * j + 2];
+        }
+    }
+    let mut best_cost = 0;
+    let mut y_dir = 0;
+    for (i, &c) in cost.iter().enumerate() {
+        if c > best_cost {
+            best_cost = c;
+            y_dir = i;
+        }
+    }
+    (y_dir, (best_cost - cost[(y_dir + 4) & 7]) >> 10)
+}
+
+fn constrain(diff: i32, threshold: i32, damping: i32) -> i32 {
+    if threshold == 0 {
+        return 0;
+    }
+    let adj = (damping - threshold.ilog2() as i32).max(0);
+    let val = diff.abs().min((threshold - (diff.abs() >> adj)).max(0));
+    val * diff.signum()
+}
+
+/// Filter one 8x8 luma block or its chroma counterpart
+#[allow(clippy::too_many_arguments)]
+fn cdef_filter(
+    f: &FrameContext,
+    src: &PlaneBuf,
+    dst: &mut PlaneBuf,
+    plane: usize,
+    r: usize,
+    c: usize,
+    pri: i32,
+    sec: i32,
+    damping: i32,
+    dir: usize,
+) {
+    let (sx, sy) = f.sub(plane);
+    let shift = f.bit_depth as i32 - 8;
+    let (x0, y0) = ((c * 4) >> sx, (r * 4) >> sy);
+    let (w, h) = (8 >> sx, 8 >> sy);
+    let (mi_rows, mi_cols) = (f.hdr.mi_rows as isize, f.hdr.mi_cols as isize);
+    let tap_set = ((pri >> shift) & 1) as usize;
+    let at = |y: isize, x: isize| -> Option<i32> {
+        let (cand_r, cand_c) = ((y << sy) >> 2, (x << sx) >> 2);
+        if y < 0 || x < 0 || cand_r >= mi_rows || cand_c >= mi_cols {
+            return None;
+        }
+        Some(*src.get(y as usize, x as usize) as i32)
+    };
+    for i in 0..h {
+        for j in 0..w {
+            let (y, x) = ((y0 + i) as isize, (x0 + j) as isize);
+            let v = *src.get(y as usize, x as usize) as i32;
+            let (mut sum, mut max, mut min) = (0, v, v);
+            for k in 0..2 {
+                for sign in [-1isize, 1] {
+                    let d = CDEF_DIRECTIONS[dir][k];
+                    if let Some(p) = at(y + sign * d[0] as isize, x + sign * d[1] as isize) {
+                        sum += CDEF_PRI_TAPS[tap_set][k] * constrain(p - v, pri, damping);
+                        max = max.max(p);
+                        min = min.min(p);
+                    }
+                    for off in [6, 2] {
+                        let d = CDEF_DIRECTIONS[(dir + off) & 7][k];
+                        if let Some(s) = at(y + sign * d[0] as isize, x + sign * d[1] as isize) {
+                            sum += CDEF_SEC_TAPS[tap_set][k] * constrain(s - v, sec, damping);
+                            max = max.max(s);
+                            min = min.min(s);
+                        }
+                    }
+                }
+            }
+            let out = v + ((8 + sum - (sum < 0) as i32) >> 4);
+            *dst.get_mut(y as usize, x as usize) = out.clamp(min, max) as u16;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constrain() {
+        assert_eq!(constrain(10, 0, 3), 0);
+        assert_eq!(constrain(3, 4, 3), 3);
+        assert_eq!(constrain(-5, 4, 3), -2);
+        assert_eq!(constrain(40, 4, 3), 0);
+    }
+
+    #[test]
+    fn test_direction_of_vertical_stripes() {
+        let mut plane = PlaneBuf::new(0, 0, 8, 8, 0);
+        for y in 0..8 {
+            for x in 0..8 {
+                *plane.get_mut(y, x) = if x < 4 { 40 } else { 200 };
+            }
+        }
+        let (dir, var) = cdef_direction(&plane, 0, 0, 0);
+        assert_eq!(dir, 6);
+        assert!(var > 0);
+    }
+}
@@ -0,0 +1,19 @@
+//! Test Streams
+//!
+//! Small AV1 streams for this crate's and dependent crates' tests.
+
+/// Temporal units of a 16x16 stream: a key frame with its sequence header,
+/// then an inter frame
+pub const TEST_STREAM: [&[u8]; 2] = [
+    &[
+        0x12, 0x00, 0x0A, 0x0A, 0x00, 0x00, 0x00, 0xF8, 0xCF, 0xFC, 0x42, 0x14,
+        0x01, 0x40, 0x32, 0x20, 0x10, 0x03, 0xE3, 0x1D, 0xF5, 0xD6, 0xE1, 0xC4,
+        0x7F, 0x20, 0x10, 0x20, 0x08, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03,
+        0xFF, 0xFF, 0xFC, 0x10, 0x18, 0x41, 0x6A, 0xB3, 0x37, 0x73, 0x70, 0xE1,
+    ],
+    &[
+        0x12, 0x00, 0x32, 0x15, 0x30, 0x0A, 0x02, 0x00, 0x00, 0x00, 0x07, 0xFD,
+        0xF5, 0xDB, 0xE8, 0xC9, 0x62, 0xCB, 0x2C, 0xB0, 0x28, 0x00, 0x7A, 0x99,
+        0x70,
+    ],
+];
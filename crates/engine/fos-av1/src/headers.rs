@@ -0,0 +1,1254 @@
+//! AV1 OBU, Sequence and Frame Headers
+//!
+//! Parsing of the uncompressed headers, including the frame state a header
+//! inherits from its primary reference frame.
+
+use super::symbol::inverse_recenter;
+use super::tables::{SEGMENTATION_FEATURE_BITS, SEGMENTATION_FEATURE_MAX, SEGMENTATION_FEATURE_SIGNED};
+use crate::bits::BitReader;
+use crate::{Av1Error, Av1Result};
+use std::sync::Arc;
+
+pub(super) const NUM_REF_FRAMES: usize = 8;
+pub(super) const REFS_PER_FRAME: usize = 7;
+pub(super) const PRIMARY_REF_NONE: usize = 7;
+
+pub(super) const INTRA_FRAME: usize = 0;
+pub(super) const LAST_FRAME: usize = 1;
+pub(super) const LAST2_FRAME: usize = 2;
+pub(super) const LAST3_FRAME: usize = 3;
+pub(super) const GOLDEN_FRAME: usize = 4;
+pub(super) const BWDREF_FRAME: usize = 5;
+pub(super) const ALTREF2_FRAME: usize = 6;
+pub(super) const ALTREF_FRAME: usize = 7;
+
+pub(super) const KEY_FRAME: u8 = 0;
+pub(super) const INTER_FRAME: u8 = 1;
+pub(super) const INTRA_ONLY_FRAME: u8 = 2;
+pub(super) const SWITCH_FRAME: u8 = 3;
+
+/// Interpolation filters; `SWITCHABLE` is signalled per block
+pub(super) const EIGHTTAP: u8 = 0;
+pub(super) const BILINEAR: u8 = 3;
+pub(super) const SWITCHABLE: u8 = 4;
+
+pub(super) const ONLY_4X4: u8 = 0;
+pub(super) const TX_MODE_LARGEST: u8 = 1;
+pub(super) const TX_MODE_SELECT: u8 = 2;
+
+pub(super) const RESTORE_NONE: u8 = 0;
+pub(super) const RESTORE_WIENER: u8 = 1;
+pub(super) const RESTORE_SGRPROJ: u8 = 2;
+pub(super) const RESTORE_SWITCHABLE: u8 = 3;
+
+pub(super) const IDENTITY: u8 = 0;
+pub(super) const TRANSLATION: u8 = 1;
+pub(super) const ROTZOOM: u8 = 2;
+pub(super) const AFFINE: u8 = 3;
+pub(super) const WARPEDMODEL_PREC_BITS: u32 = 16;
+
+pub(super) const SEG_LVL_ALT_Q: usize = 0;
+pub(super) const SEG_LVL_ALT_LF_Y_V: usize = 1;
+pub(super) const SEG_LVL_REF_FRAME: usize = 5;
+pub(super) const SEG_LVL_SKIP: usize = 6;
+pub(super) const SEG_LVL_GLOBALMV: usize = 7;
+pub(super) const MAX_SEGMENTS: usize = 8;
+
+const SELECT_SCREEN_CONTENT_TOOLS: u8 = 2;
+const SELECT_INTEGER_MV: u8 = 2;
+
+/// AV1 OBU types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObuType {
+    SequenceHeader = 1, TemporalDelimiter = 2, FrameHeader = 3,
+    TileGroup = 4, Metadata = 5, Frame = 6, RedundantFrameHeader = 7,
+    TileList = 8, Padding = 15,
+}
+
+impl TryFrom<u8> for ObuType {
+    type Error = Av1Error;
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            1 => Ok(Self::SequenceHeader), 2 => Ok(Self::TemporalDelimiter),
+            3 => Ok(Self::FrameHeader), 4 => Ok(Self::TileGroup),
+            5 => Ok(Self::Metadata), 6 => Ok(Self::Frame),
+            7 => Ok(Self::RedundantFrameHeader), 8 => Ok(Self::TileList),
+            15 => Ok(Self::Padding),
+            _ => Err(Av1Error::InvalidBitstream(format!("Invalid OBU type {}", v))),
+        }
+    }
+}
+
+/// AV1 Frame types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Av1FrameType { KeyFrame, InterFrame, IntraOnlyFrame, SwitchFrame }
+
+/// One OBU of a temporal unit
+#[derive(Debug)]
+pub(super) struct Obu<'a> {
+    pub obu_type: Option<ObuType>,
+    pub temporal_id: u8,
+    pub spatial_id: u8,
+    pub has_extension: bool,
+    pub payload: &'a [u8],
+}
+
+fn read_leb128(data: &[u8], pos: &mut usize) -> Av1Result<u64> {
+    let mut value = 0u64;
+    for i in 0..8 {
+        let byte = *data.get(*pos).ok_or(Av1Error::NeedMoreData)?;
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << (i * 7);
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Ok(value)
+}
+
+/// Split a temporal unit (low overhead bitstream format) into OBUs
+pub(super) fn parse_obus(data: &[u8]) -> Av1Result<Vec<Obu<'_>>> {
+    let mut obus = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let header = data[pos];
+        if header & 0x80 != 0 {
+            return Err(Av1Error::InvalidBitstream("OBU forbidden bit set".into()));
+        }
+        let has_extension = header & 4 != 0;
+        let has_size = header & 2 != 0;
+        pos += 1;
+        let (mut temporal_id, mut spatial_id) = (0, 0);
+        if has_extension {
+            let ext = *data.get(pos).ok_or(Av1Error::NeedMoreData)?;
+            temporal_id = ext >> 5;
+            spatial_id = (ext >> 3) & 3;
+            pos += 1;
+        }
+        let size = if has_size { read_leb128(data, &mut pos)? as usize } else { data.len() - pos };
+        let payload = data.get(pos..pos + size).ok_or(Av1Error::NeedMoreData)?;
+        obus.push(Obu { obu_type: ObuType::try_from((header >> 3) & 0xF).ok(), temporal_id, spatial_id, has_extension, payload });
+        pos += size;
+    }
+    Ok(obus)
+}
+
+/// AV1 fixed-width syntax elements
+pub(super) trait Av1Bits {
+    fn f(&mut self, n: u32) -> Av1Result<u32>;
+    fn flag(&mut self) -> Av1Result<bool> {
+        Ok(self.f(1)? != 0)
+    }
+    fn su(&mut self, n: u32) -> Av1Result<i32> {
+        let value = self.f(n)? as i32;
+        let sign = 1 << (n - 1);
+        Ok(if value & sign != 0 { value - 2 * sign } else { value })
+    }
+    fn ns(&mut self, n: u32) -> Av1Result<u32> {
+        let w = n.ilog2() + 1;
+        let m = (1 << w) - n;
+        let v = self.f(w - 1)?;
+        if v < m {
+            return Ok(v);
+        }
+        Ok((v << 1) - m + self.f(1)?)
+    }
+    fn uvlc(&mut self) -> Av1Result<u32> {
+        let mut leading_zeros = 0;
+        while !self.flag()? {
+            leading_zeros += 1;
+        }
+        if leading_zeros >= 32 {
+            return Ok(u32::MAX);
+        }
+        Ok(self.f(leading_zeros)? + ((1u64 << leading_zeros) - 1) as u32)
+    }
+}
+
+impl Av1Bits for BitReader<'_> {
+    fn f(&mut self, n: u32) -> Av1Result<u32> {
+        if n == 0 {
+            return Ok(0);
+        }
+        self.read_bits(n)
+    }
+}
+
+/// AV1 Sequence Header
+#[derive(Debug, Clone, Default)]
+pub struct Av1SequenceHeader {
+    pub seq_profile: u8, pub still_picture: bool, pub reduced_still_picture_header: bool,
+    pub max_frame_width: u32, pub max_frame_height: u32,
+    pub bit_depth: u8, pub mono_chrome: bool, pub color_primaries: u8,
+    pub transfer_characteristics: u8, pub matrix_coefficients: u8,
+    pub color_range: bool,
+    pub subsampling_x: bool, pub subsampling_y: bool,
+    pub separate_uv_delta_q: bool,
+    pub use_128x128_superblock: bool, pub enable_filter_intra: bool,
+    pub enable_intra_edge_filter: bool, pub enable_interintra_compound: bool,
+    pub enable_masked_compound: bool, pub enable_warped_motion: bool,
+    pub enable_dual_filter: bool, pub enable_order_hint: bool,
+    pub enable_jnt_comp: bool, pub enable_ref_frame_mvs: bool,
+    pub order_hint_bits: u8, pub enable_superres: bool,
+    pub enable_cdef: bool, pub enable_restoration: bool,
+    pub film_grain_params_present: bool,
+    /// Layers of the decoded operating point (0: all)
+    pub operating_point_idc: u16,
+    frame_width_bits: u32,
+    frame_height_bits: u32,
+    frame_id_numbers_present: bool,
+    delta_frame_id_length: u32,
+    additional_frame_id_length: u32,
+    seq_force_screen_content_tools: u8,
+    seq_force_integer_mv: u8,
+    decoder_model_info_present: bool,
+    equal_picture_interval: bool,
+    buffer_removal_time_length: u32,
+    frame_presentation_time_length: u32,
+    /// Operating points with a decoder model: their layer mask
+    decoder_model_ops: Vec<u16>,
+}
+
+impl Av1SequenceHeader {
+    pub fn parse(data: &[u8]) -> Av1Result<Self> {
+        let mut r = BitReader::new(data);
+        let mut seq = Self { seq_profile: r.f(3)? as u8, still_picture: r.flag()?, reduced_still_picture_header: r.flag()?, ..Default::default() };
+        if seq.seq_profile > 2 {
+            return Err(Av1Error::Unsupported(format!("AV1 profile {}", seq.seq_profile)));
+        }
+        if seq.reduced_still_picture_header {
+            r.f(5)?;
+        } else {
+            let mut buffer_delay_length = 0;
+            if r.flag()? {
+                // timing_info
+                r.f(32)?;
+                r.f(32)?;
+                seq.equal_picture_interval = r.flag()?;
+                if seq.equal_picture_interval {
+                    r.uvlc()?;
+                }
+                seq.decoder_model_info_present = r.flag()?;
+                if seq.decoder_model_info_present {
+                    buffer_delay_length = r.f(5)? + 1;
+                    r.f(32)?;
+                    seq.buffer_removal_time_length = r.f(5)? + 1;
+                    seq.frame_presentation_time_length = r.f(5)? + 1;
+                }
+            }
+            let initial_display_delay_present = r.flag()?;
+            let operating_points = r.f(5)? + 1;
+            for i in 0..operating_points {
+                let idc = r.f(12)? as u16;
+                if i == 0 {
+                    seq.operating_point_idc = idc;
+                }
+                if r.f(5)? > 7 {
+                    r.f(1)?;
+                }
+                if seq.decoder_model_info_present && r.flag()? {
+                    seq.decoder_model_ops.push(idc);
+                    r.f(buffer_delay_length)?;
+                    r.f(buffer_delay_length)?;
+                    r.f(1)?;
+                } else if seq.decoder_model_info_present {
+                    seq.decoder_model_ops.push(u16::MAX);
+                }
+                if initial_display_delay_present && r.flag()? {
+                    r.f(4)?;
+                }
+            }
+        }
+        seq.frame_width_bits = r.f(4)? + 1;
+        seq.frame_height_bits = r.f(4)? + 1;
+        seq.max_frame_width = r.f(seq.frame_width_bits)? + 1;
+        seq.max_frame_height = r.f(seq.frame_height_bits)? + 1;
+        if !seq.reduced_still_picture_header {
+            seq.frame_id_numbers_present = r.flag()?;
+        }
+        if seq.frame_id_numbers_present {
+            seq.delta_frame_id_length = r.f(4)? + 2;
+            seq.additional_frame_id_length = r.f(3)? + 1;
+        }
+        seq.use_128x128_superblock = r.flag()?;
+        seq.enable_filter_intra = r.flag()?;
+        seq.enable_intra_edge_filter = r.flag()?;
+        seq.seq_force_screen_content_tools = SELECT_SCREEN_CONTENT_TOOLS;
+        seq.seq_force_integer_mv = SELECT_INTEGER_MV;
+        if !seq.reduced_still_picture_header {
+            seq.enable_interintra_compound = r.flag()?;
+            seq.enable_masked_compound = r.flag()?;
+            seq.enable_warped_motion = r.flag()?;
+            seq.enable_dual_filter = r.flag()?;
+            seq.enable_order_hint = r.flag()?;
+            if seq.enable_order_hint {
+                seq.enable_jnt_comp = r.flag()?;
+                seq.enable_ref_frame_mvs = r.flag()?;
+            }
+            if !r.flag()? {
+                seq.seq_force_screen_content_tools = r.f(1)? as u8;
+            }
+            if seq.seq_force_screen_content_tools > 0 && !r.flag()? {
+                seq.seq_force_integer_mv = r.f(1)? as u8;
+            }
+            if seq.enable_order_hint {
+                seq.order_hint_bits = r.f(3)? as u8 + 1;
+            }
+        }
+        seq.enable_superres = r.flag()?;
+        seq.enable_cdef = r.flag()?;
+        seq.enable_restoration = r.flag()?;
+        seq.parse_color_config(&mut r)?;
+        seq.film_grain_params_present = r.flag()?;
+        Ok(seq)
+    }
+
+    fn parse_color_config(&mut self, r: &mut BitReader) -> Av1Result<()> {
+        let high_bitdepth = r.flag()?;
+        self.bit_depth = if self.seq_profile == 2 && high_bitdepth {
+            if r.flag()? { 12 } else { 10 }
+        } else if high_bitdepth {
+            10
+        } else {
+            8
+        };
+        self.mono_chrome = self.seq_profile != 1 && r.flag()?;
+        (self.color_primaries, self.transfer_characteristics, self.matrix_coefficients) = (2, 2, 2);
+        if r.flag()? {
+            self.color_primaries = r.f(8)? as u8;
+            self.transfer_characteristics = r.f(8)? as u8;
+            self.matrix_coefficients = r.f(8)? as u8;
+        }
+        if self.mono_chrome {
+            self.color_range = r.flag()?;
+            (self.subsampling_x, self.subsampling_y) = (true, true);
+            return Ok(());
+        }
+        if self.color_primaries == 1 && self.transfer_characteristics == 13 && self.matrix_coefficients == 0 {
+            // sRGB: full range 4:4:4
+            self.color_range = true;
+        } else {
+            self.color_range = r.flag()?;
+            (self.subsampling_x, self.subsampling_y) = match self.seq_profile {
+                0 => (true, true),
+                1 => (false, false),
+                _ if self.bit_depth == 12 => {
+                    let x = r.flag()?;
+                    (x, x && r.flag()?)
+                }
+                _ => (true, false),
+            };
+            if self.subsampling_x && self.subsampling_y {
+                r.f(2)?;
+            }
+        }
+        self.separate_uv_delta_q = r.flag()?;
+        Ok(())
+    }
+
+    pub fn num_planes(&self) -> usize {
+        if self.mono_chrome { 1 } else { 3 }
+    }
+
+    /// Whether an OBU of the given layers belongs to the decoded operating point
+    pub(super) fn in_operating_point(&self, temporal_id: u8, spatial_id: u8) -> bool {
+        let idc = self.operating_point_idc;
+        idc == 0 || ((idc >> temporal_id) & 1 != 0 && (idc >> (spatial_id + 8)) & 1 != 0)
+    }
+}
+
+/// Tile layout of a frame, in 4x4 mode info units
+#[derive(Debug, Clone, Default)]
+pub(super) struct TileInfo {
+    pub cols: usize,
+    pub rows: usize,
+    pub cols_log2: u32,
+    pub rows_log2: u32,
+    pub mi_col_starts: Vec<usize>,
+    pub mi_row_starts: Vec<usize>,
+    pub context_update_tile_id: usize,
+    pub tile_size_bytes: usize,
+}
+
+/// Film grain synthesis parameters (parsed, not applied)
+#[derive(Debug, Clone, Default)]
+pub(super) struct FilmGrainParams {
+    pub apply_grain: bool,
+    pub grain_seed: u16,
+    pub point_y: Vec<(u8, u8)>,
+    pub chroma_scaling_from_luma: bool,
+    pub point_cb: Vec<(u8, u8)>,
+    pub point_cr: Vec<(u8, u8)>,
+    pub grain_scaling: u8,
+    pub ar_coeff_lag: u8,
+    pub ar_coeffs_y: Vec<i8>,
+    pub ar_coeffs_cb: Vec<i8>,
+    pub ar_coeffs_cr: Vec<i8>,
+    pub ar_coeff_shift: u8,
+    pub grain_scale_shift: u8,
+    pub cb_mult: u8,
+    pub cb_luma_mult: u8,
+    pub cb_offset: u16,
+    pub cr_mult: u8,
+    pub cr_luma_mult: u8,
+    pub cr_offset: u16,
+    pub overlap: bool,
+    pub clip_to_restricted_range: bool,
+}
+
+/// Uncompressed frame header, with the state inherited from the primary
+/// reference frame resolved
+#[derive(Debug, Clone, Default)]
+pub(super) struct FrameHeader {
+    pub show_existing_frame: bool,
+    pub frame_to_show_map_idx: usize,
+    pub frame_type: u8,
+    pub frame_is_intra: bool,
+    pub show_frame: bool,
+    pub showable_frame: bool,
+    pub error_resilient_mode: bool,
+    pub disable_cdf_update: bool,
+    pub allow_screen_content_tools: bool,
+    pub force_integer_mv: bool,
+    pub current_frame_id: u32,
+    pub order_hint: u32,
+    pub primary_ref_frame: usize,
+    pub refresh_frame_flags: u8,
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub upscaled_width: u32,
+    pub render_width: u32,
+    pub render_height: u32,
+    pub use_superres: bool,
+    pub superres_denom: u32,
+    pub mi_cols: usize,
+    pub mi_rows: usize,
+    pub allow_intrabc: bool,
+    /// Slot of each of LAST_FRAME..ALTREF_FRAME
+    pub ref_frame_idx: [usize; REFS_PER_FRAME],
+    pub allow_high_precision_mv: bool,
+    pub interpolation_filter: u8,
+    pub is_motion_mode_switchable: bool,
+    pub use_ref_frame_mvs: bool,
+    /// Order hint of each reference frame, indexed by reference frame
+    pub order_hints: [u32; NUM_REF_FRAMES],
+    pub ref_frame_sign_bias: [bool; NUM_REF_FRAMES],
+    pub disable_frame_end_update_cdf: bool,
+    pub tile_info: TileInfo,
+    pub base_q_idx: i32,
+    pub delta_q_y_dc: i32,
+    pub delta_q_u_dc: i32,
+    pub delta_q_u_ac: i32,
+    pub delta_q_v_dc: i32,
+    pub delta_q_v_ac: i32,
+    pub using_qmatrix: bool,
+    pub qm_y: u8,
+    pub qm_u: u8,
+    pub qm_v: u8,
+    pub segmentation_enabled: bool,
+    pub segmentation_update_map: bool,
+    pub segmentation_temporal_update: bool,
+    pub feature_enabled: [[bool; 8]; MAX_SEGMENTS],
+    pub feature_data: [[i32; 8]; MAX_SEGMENTS],
+    pub seg_id_pre_skip: bool,
+    pub last_active_seg_id: u8,
+    pub delta_q_present: bool,
+    pub delta_q_res: u32,
+    pub delta_lf_present: bool,
+    pub delta_lf_res: u32,
+    pub delta_lf_multi: bool,
+    pub coded_lossless: bool,
+    pub all_lossless: bool,
+    pub lossless_array: [bool; MAX_SEGMENTS],
+    pub loop_filter_level: [u8; 4],
+    pub loop_filter_sharpness: u8,
+    pub loop_filter_delta_enabled: bool,
+    pub loop_filter_ref_deltas: [i32; NUM_REF_FRAMES],
+    pub loop_filter_mode_deltas: [i32; 2],
+    pub cdef_damping: u8,
+    pub cdef_bits: u32,
+    pub cdef_y_pri_strength: [u8; 8],
+    pub cdef_y_sec_strength: [u8; 8],
+    pub cdef_uv_pri_strength: [u8; 8],
+    pub cdef_uv_sec_strength: [u8; 8],
+    pub frame_restoration_type: [u8; 3],
+    pub loop_restoration_size: [usize; 3],
+    pub uses_lr: bool,
+    pub tx_mode: u8,
+    pub reference_select: bool,
+    pub skip_mode_present: bool,
+    pub skip_mode_frame: [usize; 2],
+    pub allow_warped_motion: bool,
+    pub reduced_tx_set: bool,
+    pub gm_type: [u8; NUM_REF_FRAMES],
+    pub gm_params: [[i32; 6]; NUM_REF_FRAMES],
+    pub film_grain: FilmGrainParams,
+    /// Header length in bytes, up to the end of its byte alignment
+    pub header_bytes: usize,
+}
+
+/// Reference slots as seen by the header parser
+pub(super) type RefHeaders = [Option<Arc<FrameHeader>>; NUM_REF_FRAMES];
+
+const DEFAULT_LF_REF_DELTAS: [i32; NUM_REF_FRAMES] = [1, 0, 0, 0, -1, 0, -1, -1];
+
+fn default_gm_params() -> [[i32; 6]; NUM_REF_FRAMES] {
+    [[0, 0, 1 << WARPEDMODEL_PREC_BITS, 0, 0, 1 << WARPEDMODEL_PREC_BITS]; NUM_REF_FRAMES]
+}
+
+fn tile_log2(blk_size: usize, target: usize) -> u32 {
+    let mut k = 0;
+    while (blk_size << k) < target {
+        k += 1;
+    }
+    k
+}
+
+fn invalid(msg: &str) -> Av1Error {
+    Av1Error::InvalidBitstream(msg.into())
+}
+
+impl FrameHeader {
+    /// Signed distance between two order hints
+    pub fn relative_dist(seq: &Av1SequenceHeader, a: u32, b: u32) -> i32 {
+        if !seq.enable_order_hint {
+            return 0;
+        }
+        let bits = seq.order_hint_bits as u32;
+        let diff = a as i32 - b as i32;
+        let m = 1 << (bits - 1);
+        (diff & (m - 1)) - (diff & m)
+    }
+
+    /// Quantizer index of a segment, optionally with the block's delta
+    pub fn qindex(&self, segment_id: usize, current_q_index: Option<i32>) -> i32 {
+        let base = current_q_index.unwrap_or(self.base_q_idx);
+        if self.segmentation_enabled && self.feature_enabled[segment_id][SEG_LVL_ALT_Q] {
+            (base + self.feature_data[segment_id][SEG_LVL_ALT_Q]).clamp(0, 255)
+        } else {
+            base
+        }
+    }
+
+    /// Parse an uncompressed header. `temporal_id`/`spatial_id` come from
+    /// the OBU extension.
+    pub fn parse(
+        data: &[u8],
+        seq: &Av1SequenceHeader,
+        refs: &RefHeaders,
+        temporal_id: u8,
+        spatial_id: u8,
+    ) -> Av1Result<Self> {
+        let mut r = BitReader::new(data);
+        let mut h = Self { superres_denom: 8, ..Default::default() };
+        let id_len = seq.additional_frame_id_length + seq.delta_frame_id_length;
+        let mut ref_valid: [bool; NUM_REF_FRAMES] = std::array::from_fn(|i| refs[i].is_some());
+        let mut ref_order_hint: [u32; NUM_REF_FRAMES] = std::array::from_fn(|i| refs[i].as_ref().map_or(0, |r| r.order_hint));
+        if seq.reduced_still_picture_header {
+            h.frame_type = KEY_FRAME;
+            h.frame_is_intra = true;
+            h.show_frame = true;
+        } else {
+            h.show_existing_frame = r.flag()?;
+            if h.show_existing_frame {
+                h.frame_to_show_map_idx = r.f(3)? as usize;
+                if seq.decoder_model_info_present && !seq.equal_picture_interval {
+                    r.f(seq.frame_presentation_time_length)?;
+                }
+                if seq.frame_id_numbers_present {
+                    r.f(id_len)?;
+                }
+                let shown = refs[h.frame_to_show_map_idx].as_ref().ok_or_else(|| invalid("Showing an empty reference slot"))?;
+                h.frame_type = shown.frame_type;
+                h.refresh_frame_flags = if h.frame_type == KEY_FRAME { 0xFF } else { 0 };
+                h.film_grain = shown.film_grain.clone();
+                h.header_bytes = r.position().div_ceil(8);
+                return Ok(h);
+            }
+            h.frame_type = r.f(2)? as u8;
+            h.frame_is_intra = h.frame_type == INTRA_ONLY_FRAME || h.frame_type == KEY_FRAME;
+            h.show_frame = r.flag()?;
+            if h.show_frame && seq.decoder_model_info_present && !seq.equal_picture_interval {
+                r.f(seq.frame_presentation_time_length)?;
+            }
+            h.showable_frame = if h.show_frame { h.frame_type != KEY_FRAME } else { r.flag()? };
+            h.error_resilient_mode =
+                h.frame_type == SWITCH_FRAME || (h.frame_type == KEY_FRAME && h.show_frame) || r.flag()?;
+        }
+        if h.frame_type == KEY_FRAME && h.show_frame {
+            ref_valid = [false; NUM_REF_FRAMES];
+            ref_order_hint = [0; NUM_REF_FRAMES];
+        }
+        h.disable_cdf_update = r.flag()?;
+        h.allow_screen_content_tools = if seq.seq_force_screen_content_tools == SELECT_SCREEN_CONTENT_TOOLS {
+            r.flag()?
+        } else {
+            seq.seq_force_screen_content_tools != 0
+        };
+        if h.allow_screen_content_tools {
+            h.force_integer_mv =
+                if seq.seq_force_integer_mv == SELECT_INTEGER_MV { r.flag()? } else { seq.seq_force_integer_mv != 0 };
+        }
+        if h.frame_is_intra {
+            h.force_integer_mv = true;
+        }
+        if seq.frame_id_numbers_present {
+            h.current_frame_id = r.f(id_len)?;
+        }
+        let frame_size_override_flag = if h.frame_type == SWITCH_FRAME {
+            true
+        } else {
+            !seq.reduced_still_picture_header && r.flag()?
+        };
+        h.order_hint = r.f(seq.order_hint_bits as u32)?;
+        h.primary_ref_frame = if h.frame_is_intra || h.error_resilient_mode { PRIMARY_REF_NONE } else { r.f(3)? as usize };
+        if seq.decoder_model_info_present && r.flag()? {
+            for &idc in &seq.decoder_model_ops {
+                if idc == u16::MAX {
+                    continue;
+                }
+                let in_temporal = (idc >> temporal_id) & 1 != 0;
+                let in_spatial = (idc >> (spatial_id + 8)) & 1 != 0;
+                if idc == 0 || (in_temporal && in_spatial) {
+                    r.f(seq.buffer_removal_time_length)?;
+                }
+            }
+        }
+        h.refresh_frame_flags = if h.frame_type == SWITCH_FRAME || (h.frame_type == KEY_FRAME && h.show_frame) {
+            0xFF
+        } else {
+            r.f(8)? as u8
+        };
+        if (!h.frame_is_intra || h.refresh_frame_flags != 0xFF) && h.error_resilient_mode && seq.enable_order_hint {
+            for i in 0..NUM_REF_FRAMES {
+                let hint = r.f(seq.order_hint_bits as u32)?;
+                if hint != ref_order_hint[i] || !ref_valid[i] {
+                    ref_order_hint[i] = hint;
+                    ref_valid[i] = false;
+                }
+            }
+        }
+        if h.frame_is_intra {
+            h.frame_size(&mut r, seq, frame_size_override_flag)?;
+            h.render_size(&mut r)?;
+            if h.allow_screen_content_tools && h.upscaled_width == h.frame_width {
+                h.allow_intrabc = r.flag()?;
+            }
+        } else {
+            let short_signaling = seq.enable_order_hint && r.flag()?;
+            if short_signaling {
+                let last = r.f(3)? as usize;
+                let gold = r.f(3)? as usize;
+                h.ref_frame_idx = set_frame_refs(seq, h.order_hint, &ref_order_hint, last, gold);
+            }
+            for i in 0..REFS_PER_FRAME {
+                if !short_signaling {
+                    h.ref_frame_idx[i] = r.f(3)? as usize;
+                }
+                if seq.frame_id_numbers_present {
+                    r.f(seq.delta_frame_id_length)?;
+                }
+            }
+            if h.ref_frame_idx.iter().any(|&i| !ref_valid[i]) {
+                return Err(invalid("Inter frame references an empty slot"));
+            }
+            if frame_size_override_flag && !h.error_resilient_mode {
+                h.frame_size_with_refs(&mut r, seq, refs)?;
+            } else {
+                h.frame_size(&mut r, seq, frame_size_override_flag)?;
+                h.render_size(&mut r)?;
+            }
+            h.allow_high_precision_mv = !h.force_integer_mv && r.flag()?;
+            h.interpolation_filter = if r.flag()? { SWITCHABLE } else { r.f(2)? as u8 };
+            h.is_motion_mode_switchable = r.flag()?;
+            h.use_ref_frame_mvs = !h.error_resilient_mode && seq.enable_ref_frame_mvs && r.flag()?;
+            for i in 0..REFS_PER_FRAME {
+                let hint = ref_order_hint[h.ref_frame_idx[i]];
+                h.order_hints[LAST_FRAME + i] = hint;
+                h.ref_frame_sign_bias[LAST_FRAME + i] = Self::relative_dist(seq, hint, h.order_hint) > 0;
+            }
+        }
+        h.disable_frame_end_update_cdf = seq.reduced_still_picture_header || h.disable_cdf_update || r.flag()?;
+        // Inherited state: loop filter deltas, segmentation features and
+        // global motion of the primary reference frame
+        let prev = if h.primary_ref_frame == PRIMARY_REF_NONE {
+            None
+        } else {
+            refs[h.ref_frame_idx[h.primary_ref_frame]].clone()
+        };
+        h.loop_filter_ref_deltas = DEFAULT_LF_REF_DELTAS;
+        if let Some(prev) = &prev {
+            h.loop_filter_ref_deltas = prev.loop_filter_ref_deltas;
+            h.loop_filter_mode_deltas = prev.loop_filter_mode_deltas;
+            h.feature_enabled = prev.feature_enabled;
+            h.feature_data = prev.feature_data;
+        }
+        h.tile_info(&mut r, seq)?;
+        h.quantization_params(&mut r, seq)?;
+        h.segmentation_params(&mut r)?;
+        if h.base_q_idx > 0 {
+            h.delta_q_present = r.flag()?;
+        }
+        if h.delta_q_present {
+            h.delta_q_res = r.f(2)?;
+            if !h.allow_intrabc {
+                h.delta_lf_present = r.flag()?;
+            }
+            if h.delta_lf_present {
+                h.delta_lf_res = r.f(2)?;
+                h.delta_lf_multi = r.flag()?;
+            }
+        }
+        h.coded_lossless = true;
+        for segment_id in 0..MAX_SEGMENTS {
+            let q = h.qindex(segment_id, None);
+            h.lossless_array[segment_id] = q == 0
+                && h.delta_q_y_dc == 0
+                && h.delta_q_u_ac == 0
+                && h.delta_q_u_dc == 0
+                && h.delta_q_v_ac == 0
+                && h.delta_q_v_dc == 0;
+            h.coded_lossless &= h.lossless_array[segment_id];
+        }
+        h.all_lossless = h.coded_lossless && h.frame_width == h.upscaled_width;
+        h.loop_filter_params(&mut r, seq)?;
+        h.cdef_params(&mut r, seq)?;
+        h.lr_params(&mut r, seq)?;
+        h.tx_mode = if h.coded_lossless {
+            ONLY_4X4
+        } else if r.flag()? {
+            TX_MODE_SELECT
+        } else {
+            TX_MODE_LARGEST
+        };
+        h.reference_select = !h.frame_is_intra && r.flag()?;
+        h.skip_mode_params(&mut r, seq, &ref_order_hint)?;
+        h.allow_warped_motion = !h.frame_is_intra && !h.error_resilient_mode && seq.enable_warped_motion && r.flag()?;
+        h.reduced_tx_set = r.flag()?;
+        let prev_gm = prev.as_ref().map_or_else(default_gm_params, |p| p.gm_params);
+        h.global_motion_params(&mut r, &prev_gm)?;
+        h.film_grain_params(&mut r, seq, refs)?;
+        h.header_bytes = r.position().div_ceil(8);
+        Ok(h)
+    }
+}
+
+impl FrameHeader {
+    fn frame_size(&mut self, r: &mut BitReader, seq: &Av1SequenceHeader, override_flag: bool) -> Av1Result<()> {
+        if override_flag {
+            self.frame_width = r.f(seq.frame_width_bits)? + 1;
+            self.frame_height = r.f(seq.frame_height_bits)? + 1;
+        } else {
+            self.frame_width = seq.max_frame_width;
+            self.frame_height = seq.max_frame_height;
+        }
+        self.superres_params(r, seq)
+    }
+
+    fn superres_params(&mut self, r: &mut BitReader, seq: &Av1SequenceHeader) -> Av1Result<()> {
+        self.use_superres = seq.enable_superres && r.flag()?;
+        self.superres_denom = if self.use_superres { r.f(3)? + 9 } else { 8 };
+        self.upscaled_width = self.frame_width;
+        self.frame_width = (self.upscaled_width * 8 + self.superres_denom / 2) / self.superres_denom;
+        self.mi_cols = 2 * ((self.frame_width as usize + 7) >> 3);
+        self.mi_rows = 2 * ((self.frame_height as usize + 7) >> 3);
+        Ok(())
+    }
+
+    fn render_size(&mut self, r: &mut BitReader) -> Av1Result<()> {
+        if r.flag()? {
+            self.render_width = r.f(16)? + 1;
+            self.render_height = r.f(16)? + 1;
+        } else {
+            self.render_width = self.upscaled_width;
+            self.render_height = self.frame_height;
+        }
+        Ok(())
+    }
+
+    fn frame_size_with_refs(&mut self, r: &mut BitReader, seq: &Av1SequenceHeader, refs: &RefHeaders) -> Av1Result<()> {
+        for i in 0..REFS_PER_FRAME {
+            if r.flag()? {
+                let rf = refs[self.ref_frame_idx[i]].as_ref().ok_or_else(|| invalid("Size from an empty slot"))?;
+                self.frame_width = rf.upscaled_width;
+                self.frame_height = rf.frame_height;
+                self.render_width = rf.render_width;
+                self.render_height = rf.render_height;
+                return self.superres_params(r, seq);
+            }
+        }
+        self.frame_size(r, seq, true)?;
+        self.render_size(r)
+    }
+
+    fn tile_info(&mut self, r: &mut BitReader, seq: &Av1SequenceHeader) -> Av1Result<()> {
+        let sb_shift = if seq.use_128x128_superblock { 5 } else { 4 };
+        let sb_cols = (self.mi_cols + (1 << sb_shift) - 1) >> sb_shift;
+        let sb_rows = (self.mi_rows + (1 << sb_shift) - 1) >> sb_shift;
+        let sb_size = sb_shift + 2;
+        let max_tile_width_sb = 4096 >> sb_size;
+        let mut max_tile_area_sb = (4096 * 2304) >> (2 * sb_size);
+        let min_log2_tile_cols = tile_log2(max_tile_width_sb, sb_cols);
+        let max_log2_tile_cols = tile_log2(1, sb_cols.min(64));
+        let max_log2_tile_rows = tile_log2(1, sb_rows.min(64));
+        let min_log2_tiles = min_log2_tile_cols.max(tile_log2(max_tile_area_sb, sb_rows * sb_cols));
+        let t = &mut self.tile_info;
+        t.mi_col_starts.clear();
+        t.mi_row_starts.clear();
+        if r.flag()? {
+            // Uniform spacing
+            t.cols_log2 = min_log2_tile_cols;
+            while t.cols_log2 < max_log2_tile_cols && r.flag()? {
+                t.cols_log2 += 1;
+            }
+            let tile_width_sb = (sb_cols + (1 << t.cols_log2) - 1) >> t.cols_log2;
+            t.mi_col_starts.extend((0..sb_cols).step_by(tile_width_sb).map(|sb| sb << sb_shift));
+            let min_log2_tile_rows = min_log2_tiles.saturating_sub(t.cols_log2);
+            t.rows_log2 = min_log2_tile_rows;
+            while t.rows_log2 < max_log2_tile_rows && r.flag()? {
+                t.rows_log2 += 1;
+            }
+            let tile_height_sb = (sb_rows + (1 << t.rows_log2) - 1) >> t.rows_log2;
+            t.mi_row_starts.extend((0..sb_rows).step_by(tile_height_sb).map(|sb| sb << sb_shift));
+        } else {
+            let mut widest_tile_sb = 0;
+            let mut start_sb = 0;
+            while start_sb < sb_cols {
+                t.mi_col_starts.push(start_sb << sb_shift);
+                let max_width = (sb_cols - start_sb).min(max_tile_width_sb);
+                let size_sb = r.ns(max_width as u32)? as usize + 1;
+                widest_tile_sb = widest_tile_sb.max(size_sb);
+                start_sb += size_sb;
+            }
+            t.cols_log2 = tile_log2(1, t.mi_col_starts.len());
+            max_tile_area_sb = if min_log2_tiles > 0 {
+                (sb_rows * sb_cols) >> (min_log2_tiles + 1)
+            } else {
+                sb_rows * sb_cols
+            };
+            let max_tile_height_sb = (max_tile_area_sb / widest_tile_sb).max(1);
+            start_sb = 0;
+            while start_sb < sb_rows {
+                t.mi_row_starts.push(start_sb << sb_shift);
+                let max_height = (sb_rows - start_sb).min(max_tile_height_sb);
+                start_sb += r.ns(max_height as u32)? as usize + 1;
+            }
+            t.rows_log2 = tile_log2(1, t.mi_row_starts.len());
+        }
+        t.cols = t.mi_col_starts.len();
+        t.rows = t.mi_row_starts.len();
+        t.mi_col_starts.push(self.mi_cols);
+        t.mi_row_starts.push(self.mi_rows);
+        if t.cols_log2 > 0 || t.rows_log2 > 0 {
+            t.context_update_tile_id = r.f(t.cols_log2 + t.rows_log2)? as usize;
+            t.tile_size_bytes = r.f(2)? as usize + 1;
+            if t.context_update_tile_id >= t.cols * t.rows {
+                return Err(invalid("Invalid context update tile"));
+            }
+        }
+        Ok(())
+    }
+
+    fn quantization_params(&mut self, r: &mut BitReader, seq: &Av1SequenceHeader) -> Av1Result<()> {
+        fn read_delta_q(r: &mut BitReader) -> Av1Result<i32> {
+            if r.flag()? { r.su(7) } else { Ok(0) }
+        }
+        self.base_q_idx = r.f(8)? as i32;
+        self.delta_q_y_dc = read_delta_q(r)?;
+        if seq.num_planes() > 1 {
+            let diff_uv_delta = seq.separate_uv_delta_q && r.flag()?;
+            self.delta_q_u_dc = read_delta_q(r)?;
+            self.delta_q_u_ac = read_delta_q(r)?;
+            if diff_uv_delta {
+                self.delta_q_v_dc = read_delta_q(r)?;
+                self.delta_q_v_ac = read_delta_q(r)?;
+            } else {
+                self.delta_q_v_dc = self.delta_q_u_dc;
+                self.delta_q_v_ac = self.delta_q_u_ac;
+            }
+        }
+        self.using_qmatrix = r.flag()?;
+        if self.using_qmatrix {
+            self.qm_y = r.f(4)? as u8;
+            self.qm_u = r.f(4)? as u8;
+            self.qm_v = if seq.separate_uv_delta_q { r.f(4)? as u8 } else { self.qm_u };
+            // The quantizer matrix tables are not bundled; level 15 is flat
+            if self.qm_y.min(self.qm_u).min(self.qm_v) < 15 {
+                return Err(Av1Error::Unsupported("AV1 quantizer matrices".into()));
+            }
+        }
+        Ok(())
+    }
+
+    fn segmentation_params(&mut self, r: &mut BitReader) -> Av1Result<()> {
+        self.segmentation_enabled = r.flag()?;
+        if self.segmentation_enabled {
+            let update_data = if self.primary_ref_frame == PRIMARY_REF_NONE {
+                self.segmentation_update_map = true;
+                self.segmentation_temporal_update = false;
+                true
+            } else {
+                self.segmentation_update_map = r.flag()?;
+                self.segmentation_temporal_update = self.segmentation_update_map && r.flag()?;
+                r.flag()?
+            };
+            if update_data {
+                for i in 0..MAX_SEGMENTS {
+                    for j in 0..8 {
+                        let enabled = r.flag()?;
+                        let bits = SEGMENTATION_FEATURE_BITS[j];
+                        let limit = SEGMENTATION_FEATURE_MAX[j];
+                        let value = match (enabled, SEGMENTATION_FEATURE_SIGNED[j]) {
+                            (false, _) => 0,
+                            (true, true) => r.su(1 + bits)?.clamp(-limit, limit),
+                            (true, false) => (r.f(bits)? as i32).clamp(0, limit),
+                        };
+                        self.feature_enabled[i][j] = enabled;
+                        self.feature_data[i][j] = value;
+                    }
+                }
+            }
+        } else {
+            self.feature_enabled = Default::default();
+            self.feature_data = Default::default();
+        }
+        self.seg_id_pre_skip = false;
+        self.last_active_seg_id = 0;
+        for i in 0..MAX_SEGMENTS {
+            for j in 0..8 {
+                if self.feature_enabled[i][j] {
+                    self.last_active_seg_id = i as u8;
+                    self.seg_id_pre_skip |= j >= SEG_LVL_REF_FRAME;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn loop_filter_params(&mut self, r: &mut BitReader, seq: &Av1SequenceHeader) -> Av1Result<()> {
+        if self.coded_lossless || self.allow_intrabc {
+            self.loop_filter_ref_deltas = DEFAULT_LF_REF_DELTAS;
+            self.loop_filter_mode_deltas = [0; 2];
+            return Ok(());
+        }
+        self.loop_filter_level[0] = r.f(6)? as u8;
+        self.loop_filter_level[1] = r.f(6)? as u8;
+        if seq.num_planes() > 1 && (self.loop_filter_level[0] != 0 || self.loop_filter_level[1] != 0) {
+            self.loop_filter_level[2] = r.f(6)? as u8;
+            self.loop_filter_level[3] = r.f(6)? as u8;
+        }
+        self.loop_filter_sharpness = r.f(3)? as u8;
+        self.loop_filter_delta_enabled = r.flag()?;
+        if self.loop_filter_delta_enabled && r.flag()? {
+            for i in 0..NUM_REF_FRAMES {
+                if r.flag()? {
+                    self.loop_filter_ref_deltas[i] = r.su(7)?;
+                }
+            }
+            for i in 0..2 {
+                if r.flag()? {
+                    self.loop_filter_mode_deltas[i] = r.su(7)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn cdef_params(&mut self, r: &mut BitReader, seq: &Av1SequenceHeader) -> Av1Result<()> {
+        self.cdef_damping = 3;
+        if self.coded_lossless || self.allow_intrabc || !seq.enable_cdef {
+            return Ok(());
+        }
+        self.cdef_damping = r.f(2)? as u8 + 3;
+        self.cdef_bits = r.f(2)?;
+        for i in 0..1 << self.cdef_bits {
+            self.cdef_y_pri_strength[i] = r.f(4)? as u8;
+            self.cdef_y_sec_strength[i] = r.f(2)? as u8;
+            if self.cdef_y_sec_strength[i] == 3 {
+                self.cdef_y_sec_strength[i] = 4;
+            }
+            if seq.num_planes() > 1 {
+                self.cdef_uv_pri_strength[i] = r.f(4)? as u8;
+                self.cdef_uv_sec_strength[i] = r.f(2)? as u8;
+                if self.cdef_uv_sec_strength[i] == 3 {
+                    self.cdef_uv_sec_strength[i] = 4;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn lr_params(&mut self, r: &mut BitReader, seq: &Av1SequenceHeader) -> Av1Result<()> {
+        if self.all_lossless || self.allow_intrabc || !seq.enable_restoration {
+            return Ok(());
+        }
+        const REMAP_LR_TYPE: [u8; 4] = [RESTORE_NONE, RESTORE_SWITCHABLE, RESTORE_WIENER, RESTORE_SGRPROJ];
+        let mut uses_chroma_lr = false;
+        for i in 0..seq.num_planes() {
+            self.frame_restoration_type[i] = REMAP_LR_TYPE[r.f(2)? as usize];
+            if self.frame_restoration_type[i] != RESTORE_NONE {
+                self.uses_lr = true;
+                uses_chroma_lr |= i > 0;
+            }
+        }
+        if self.uses_lr {
+            let mut lr_unit_shift = r.f(1)?;
+            if seq.use_128x128_superblock {
+                lr_unit_shift += 1;
+            } else if lr_unit_shift != 0 {
+                lr_unit_shift += r.f(1)?;
+            }
+            self.loop_restoration_size[0] = 256 >> (2 - lr_unit_shift);
+            let lr_uv_shift =
+                if seq.subsampling_x && seq.subsampling_y && uses_chroma_lr { r.f(1)? } else { 0 };
+            self.loop_restoration_size[1] = self.loop_restoration_size[0] >> lr_uv_shift;
+            self.loop_restoration_size[2] = self.loop_restoration_size[0] >> lr_uv_shift;
+        }
+        Ok(())
+    }
+
+    fn skip_mode_params(&mut self, r: &mut BitReader, seq: &Av1SequenceHeader, ref_order_hint: &[u32; NUM_REF_FRAMES]) -> Av1Result<()> {
+        if self.frame_is_intra || !self.reference_select || !seq.enable_order_hint {
+            return Ok(());
+        }
+        let dist = |a, b| Self::relative_dist(seq, a, b);
+        let mut forward: Option<(usize, u32)> = None;
+        let mut backward: Option<(usize, u32)> = None;
+        for i in 0..REFS_PER_FRAME {
+            let ref_hint = ref_order_hint[self.ref_frame_idx[i]];
+            if dist(ref_hint, self.order_hint) < 0 {
+                if forward.is_none_or(|(_, hint)| dist(ref_hint, hint) > 0) {
+                    forward = Some((i, ref_hint));
+                }
+            } else if dist(ref_hint, self.order_hint) > 0 && backward.is_none_or(|(_, hint)| dist(ref_hint, hint) < 0) {
+                backward = Some((i, ref_hint));
+            }
+        }
+        let Some((forward_idx, forward_hint)) = forward else {
+            return Ok(());
+        };
+        let second = match backward {
+            Some((idx, _)) => idx,
+            None => {
+                let mut second: Option<(usize, u32)> = None;
+                for i in 0..REFS_PER_FRAME {
+                    let ref_hint = ref_order_hint[self.ref_frame_idx[i]];
+                    if dist(ref_hint, forward_hint) < 0 && second.is_none_or(|(_, hint)| dist(ref_hint, hint) > 0) {
+                        second = Some((i, ref_hint));
+                    }
+                }
+                match second {
+                    Some((idx, _)) => idx,
+                    None => return Ok(()),
+                }
+            }
+        };
+        self.skip_mode_frame = [LAST_FRAME + forward_idx.min(second), LAST_FRAME + forward_idx.max(second)];
+        self.skip_mode_present = r.flag()?;
+        Ok(())
+    }
+
+    fn global_motion_params(&mut self, r: &mut BitReader, prev_gm: &[[i32; 6]; NUM_REF_FRAMES]) -> Av1Result<()> {
+        self.gm_params = default_gm_params();
+        if self.frame_is_intra {
+            return Ok(());
+        }
+        for rf in LAST_FRAME..=ALTREF_FRAME {
+            let gm_type = if !r.flag()? {
+                IDENTITY
+            } else if r.flag()? {
+                ROTZOOM
+            } else if r.flag()? {
+                TRANSLATION
+            } else {
+                AFFINE
+            };
+            self.gm_type[rf] = gm_type;
+            if gm_type >= ROTZOOM {
+                self.read_global_param(r, prev_gm, gm_type, rf, 2)?;
+                self.read_global_param(r, prev_gm, gm_type, rf, 3)?;
+                if gm_type == AFFINE {
+                    self.read_global_param(r, prev_gm, gm_type, rf, 4)?;
+                    self.read_global_param(r, prev_gm, gm_type, rf, 5)?;
+                } else {
+                    self.gm_params[rf][4] = -self.gm_params[rf][3];
+                    self.gm_params[rf][5] = self.gm_params[rf][2];
+                }
+            }
+            if gm_type >= TRANSLATION {
+                self.read_global_param(r, prev_gm, gm_type, rf, 0)?;
+                self.read_global_param(r, prev_gm, gm_type, rf, 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_global_param(
+        &mut self,
+        r: &mut BitReader,
+        prev_gm: &[[i32; 6]; NUM_REF_FRAMES],
+        gm_type: u8,
+        rf: usize,
+        idx: usize,
+    ) -> Av1Result<()> {
+        let (abs_bits, prec_bits) = if idx >= 2 {
+            (12, 15)
+        } else if gm_type == TRANSLATION {
+            let hp = !self.allow_high_precision_mv as u32;
+            (9 - hp, 3 - hp)
+        } else {
+            (12, 6)
+        };
+        let prec_diff = WARPEDMODEL_PREC_BITS - prec_bits;
+        let (round, sub) = if idx % 3 == 2 { (1 << WARPEDMODEL_PREC_BITS, 1 << prec_bits) } else { (0, 0) };
+        let mx = 1 << abs_bits;
+        let reference = (prev_gm[rf][idx] >> prec_diff) - sub;
+        let value = decode_signed_subexp_with_ref(r, -mx, mx + 1, reference)?;
+        self.gm_params[rf][idx] = (value << prec_diff) + round;
+        Ok(())
+    }
+
+    fn film_grain_params(&mut self, r: &mut BitReader, seq: &Av1SequenceHeader, refs: &RefHeaders) -> Av1Result<()> {
+        if !seq.film_grain_params_present || (!self.show_frame && !self.showable_frame) || !r.flag()? {
+            return Ok(());
+        }
+        let grain_seed = r.f(16)? as u16;
+        let update_grain = self.frame_type != INTER_FRAME || r.flag()?;
+        if !update_grain {
+            let idx = r.f(3)? as usize;
+            let rf = refs[idx].as_ref().ok_or_else(|| invalid("Film grain from an empty slot"))?;
+            self.film_grain = FilmGrainParams { grain_seed, ..rf.film_grain.clone() };
+            return Ok(());
+        }
+        let g = &mut self.film_grain;
+        g.apply_grain = true;
+        g.grain_seed = grain_seed;
+        let read_points = |r: &mut BitReader, n: u32| -> Av1Result<Vec<(u8, u8)>> {
+            (0..n).map(|_| Ok((r.f(8)? as u8, r.f(8)? as u8))).collect()
+        };
+        let num_y_points = r.f(4)?;
+        g.point_y = read_points(r, num_y_points)?;
+        g.chroma_scaling_from_luma = !seq.mono_chrome && r.flag()?;
+        if !(seq.mono_chrome || g.chroma_scaling_from_luma || (seq.subsampling_x && seq.subsampling_y && num_y_points == 0)) {
+            let n = r.f(4)?;
+            g.point_cb = read_points(r, n)?;
+            let n = r.f(4)?;
+            g.point_cr = read_points(r, n)?;
+        }
+        g.grain_scaling = r.f(2)? as u8 + 8;
+        g.ar_coeff_lag = r.f(2)? as u8;
+        let num_pos_luma = 2 * g.ar_coeff_lag as usize * (g.ar_coeff_lag as usize + 1);
+        let num_pos_chroma = num_pos_luma + (num_y_points > 0) as usize;
+        let read_coeffs = |r: &mut BitReader, n: usize| -> Av1Result<Vec<i8>> {
+            (0..n).map(|_| Ok((r.f(8)? as i32 - 128) as i8)).collect()
+        };
+        if num_y_points > 0 {
+            g.ar_coeffs_y = read_coeffs(r, num_pos_luma)?;
+        }
+        if g.chroma_scaling_from_luma || !g.point_cb.is_empty() {
+            g.ar_coeffs_cb = read_coeffs(r, num_pos_chroma)?;
+        }
+        if g.chroma_scaling_from_luma || !g.point_cr.is_empty() {
+            g.ar_coeffs_cr = read_coeffs(r, num_pos_chroma)?;
+        }
+        g.ar_coeff_shift = r.f(2)? as u8 + 6;
+        g.grain_scale_shift = r.f(2)? as u8;
+        if !g.point_cb.is_empty() {
+            g.cb_mult = r.f(8)? as u8;
+            g.cb_luma_mult = r.f(8)? as u8;
+            g.cb_offset = r.f(9)? as u16;
+        }
+        if !g.point_cr.is_empty() {
+            g.cr_mult = r.f(8)? as u8;
+            g.cr_luma_mult = r.f(8)? as u8;
+            g.cr_offset = r.f(9)? as u16;
+        }
+        g.overlap = r.flag()?;
+        g.clip_to_restricted_range = r.flag()?;
+        Ok(())
+    }
+}
+
+fn decode_signed_subexp_with_ref(r: &mut BitReader, low: i32, high: i32, reference: i32) -> Av1Result<i32> {
+    let mx = high - low;
+    let reference = reference - low;
+    // decode_subexp with k = 3
+    let mut i = 0;
+    let mut mk = 0;
+    let v = loop {
+        let b2 = if i > 0 { 3 + i - 1 } else { 3 };
+        let a = 1 << b2;
+        if mx <= mk + 3 * a {
+            break r.ns((mx - mk) as u32)? as i32 + mk;
+        }
+        if !r.flag()? {
+            break r.f(b2)? as i32 + mk;
+        }
+        i += 1;
+        mk += a;
+    };
+    let x = if (reference << 1) <= mx {
+        inverse_recenter(reference, v)
+    } else {
+        mx - 1 - inverse_recenter(mx - 1 - reference, v)
+    };
+    Ok(x + low)
+}
+
+/// Reference slots implied by `frame_refs_short_signaling`
+fn set_frame_refs(
+    seq: &Av1SequenceHeader,
+    order_hint: u32,
+    ref_order_hint: &[u32; NUM_REF_FRAMES],
+    last: usize,
+    gold: usize,
+) -> [usize; REFS_PER_FRAME] {
+    let mut idx = [-1i32; REFS_PER_FRAME];
+    idx[0] = last as i32;
+    idx[GOLDEN_FRAME - LAST_FRAME] = gold as i32;
+    let mut used = [false; NUM_REF_FRAMES];
+    used[last] = true;
+    used[gold] = true;
+    let cur_frame_hint = 1 << (seq.order_hint_bits - 1);
+    let shifted: [i32; NUM_REF_FRAMES] =
+        std::array::from_fn(|i| cur_frame_hint + FrameHeader::relative_dist(seq, ref_order_hint[i], order_hint));
+    // Latest backward reference is ALTREF, the earliest ones BWDREF and ALTREF2
+    let find = |used: &[bool; NUM_REF_FRAMES], backward: bool, latest: bool| -> Option<usize> {
+        let mut best: Option<(usize, i32)> = None;
+        for (i, &hint) in shifted.iter().enumerate() {
+            if used[i] || (hint >= cur_frame_hint) != backward {
+                continue;
+            }
+            let better = match best {
+                None => true,
+                Some((_, b)) if latest => hint >= b,
+                Some((_, b)) => hint < b,
+            };
+            if better {
+                best = Some((i, hint));
+            }
+        }
+        best.map(|(i, _)| i)
+    };
+    for (rf, latest) in [(ALTREF_FRAME, true), (BWDREF_FRAME, false), (ALTREF2_FRAME, false)] {
+        if let Some(r) = find(&used, true, latest) {
+            idx[rf - LAST_FRAME] = r as i32;
+            used[r] = true;
+        }
+    }
+    for rf in [LAST2_FRAME, LAST3_FRAME, BWDREF_FRAME, ALTREF2_FRAME, ALTREF_FRAME] {
+        if idx[rf - LAST_FRAME] < 0 && let Some(r) = find(&used, false, true) {
+            idx[rf - LAST_FRAME] = r as i32;
+            used[r] = true;
+        }
+    }
+    // Anything left points at the earliest frame
+    let mut earliest: Option<(usize, i32)> = None;
+    for (i, &hint) in shifted.iter().enumerate() {
+        if earliest.is_none_or(|(_, e)| hint < e) {
+            earliest = Some((i, hint));
+        }
+    }
+    let fallback = earliest.map_or(0, |(i, _)| i) as i32;
+    idx.map(|i| if i < 0 { fallback } else { i } as usize)
+}
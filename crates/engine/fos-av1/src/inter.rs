@@ -0,0 +1,649 @@
+//! AV1 Inter Prediction
+//!
+//! Motion compensation from reference frames, warped motion, compound and
+//! inter-intra blending masks, and overlapped block motion compensation.
+
+use super::headers::*;
+use super::state::{MiInfo, Mv, PlaneBuf};
+use super::tables::*;
+use super::tile::{TileDecoder, COMPOUND_DIFFWTD, COMPOUND_DISTANCE, COMPOUND_WEDGE, LOCALWARP, OBMC};
+use std::sync::OnceLock;
+
+const WARPEDMODEL_PREC_BITS: u32 = 16;
+const WARPEDMODEL_NONDIAGAFFINE_CLAMP: i64 = 1 << 13;
+const WARPEDMODEL_TRANS_CLAMP: i64 = 1 << 23;
+const WARP_PARAM_REDUCE_BITS: u32 = 6;
+const WARPEDDIFF_PREC_BITS: u32 = 10;
+const WARPEDPIXEL_PREC_SHIFTS: i32 = 64;
+const DIV_LUT_BITS: u32 = 8;
+const DIV_LUT_PREC_BITS: u32 = 14;
+const LS_MV_MAX: i32 = 256;
+const SCALE_SUBPEL_BITS: u32 = 10;
+const REF_SCALE_SHIFT: u32 = 14;
+const SUBPEL_BITS: u32 = 4;
+const MAX_FRAME_DISTANCE: i32 = 31;
+const MASK_MASTER_SIZE: usize = 64;
+
+const II_DC_PRED: u8 = 0;
+const II_V_PRED: u8 = 1;
+const II_H_PRED: u8 = 2;
+
+fn round2(x: i32, n: u32) -> i32 {
+    if n == 0 { x } else { (x + (1 << (n - 1))) >> n }
+}
+
+fn round2_signed(x: i64, n: u32) -> i64 {
+    if n == 0 {
+        x
+    } else if x >= 0 {
+        (x + (1 << (n - 1))) >> n
+    } else {
+        -((-x + (1 << (n - 1))) >> n)
+    }
+}
+
+/// Rounding of the horizontal and vertical filter passes and of the final
+/// sample
+fn rounding(bit_depth: u8, compound: bool) -> (u32, u32, u32) {
+    let r0 = if bit_depth == 12 { 5 } else { 3 };
+    let r1 = if compound { 7 } else { 14 - r0 };
+    (r0, r1, 14 - r0 - r1)
+}
+
+/// Subpel filter for a direction: blocks of 4 samples or less use the
+/// 4-tap variants
+fn filter_index(filter: u8, size: usize) -> usize {
+    match filter {
+        0 | 2 if size <= 4 => 4,
+        1 if size <= 4 => 5,
+        f => f as usize,
+    }
+}
+
+fn resolve_divisor(d: i64) -> (u32, i64) {
+    let n = d.unsigned_abs().ilog2();
+    let e = d.abs() - (1 << n);
+    let f = if n > DIV_LUT_BITS { round2_signed(e, n - DIV_LUT_BITS) } else { e << (DIV_LUT_BITS - n) };
+    let factor = div_lut(f as usize) as i64;
+    (n + DIV_LUT_PREC_BITS, if d < 0 { -factor } else { factor })
+}
+
+/// Shear parameters of an affine model, when it suits the warp filter
+fn setup_shear(p: &[i32; 6]) -> Option<[i32; 4]> {
+    let clamp16 = |v: i64| v.clamp(i16::MIN as i64, i16::MAX as i64);
+    let alpha0 = clamp16(p[2] as i64 - (1 << WARPEDMODEL_PREC_BITS));
+    let beta0 = clamp16(p[3] as i64);
+    let (shift, factor) = resolve_divisor(p[2] as i64);
+    let v = (p[4] as i64) << WARPEDMODEL_PREC_BITS;
+    let gamma0 = clamp16(round2_signed(v * factor, shift));
+    let w = p[3] as i64 * p[4] as i64;
+    let delta0 = clamp16(p[5] as i64 - round2_signed(w * factor, shift) - (1 << WARPEDMODEL_PREC_BITS));
+    let reduce = |v: i64| (round2_signed(v, WARP_PARAM_REDUCE_BITS) << WARP_PARAM_REDUCE_BITS) as i32;
+    let [alpha, beta, gamma, delta] = [alpha0, beta0, gamma0, delta0].map(reduce);
+    if 4 * alpha.abs() + 7 * beta.abs() >= 1 << WARPEDMODEL_PREC_BITS {
+        return None;
+    }
+    if 4 * gamma.abs() + 4 * delta.abs() >= 1 << WARPEDMODEL_PREC_BITS {
+        return None;
+    }
+    Some([alpha, beta, gamma, delta])
+}
+
+/// Mask of the six wedge directions over the master square, and its
+/// complement
+fn wedge_master() -> &'static [[Vec<u8>; 6]; 2] {
+    static MASTER: OnceLock<[[Vec<u8>; 6]; 2]> = OnceLock::new();
+    MASTER.get_or_init(|| {
+        let n = MASK_MASTER_SIZE;
+        let mut m: [[Vec<u8>; 6]; 2] = std::array::from_fn(|_| std::array::from_fn(|_| vec![0; n * n]));
+        let shift_copy = |src: &[u8; 64], dst: &mut [u8], shift: isize| {
+            for (i, d) in dst.iter_mut().enumerate() {
+                *d = src[(i as isize - shift).clamp(0, n as isize - 1) as usize];
+            }
+        };
+        let mut shift = (n / 4) as isize;
+        for i in (0..n).step_by(2) {
+            let obl = &mut m[0][WEDGE_OBLIQUE63 as usize];
+            shift_copy(&WEDGE_MASTER_OBLIQUE_EVEN, &mut obl[i * n..(i + 1) * n], shift);
+            shift -= 1;
+            shift_copy(&WEDGE_MASTER_OBLIQUE_ODD, &mut obl[(i + 1) * n..(i + 2) * n], shift);
+            let vert = &mut m[0][WEDGE_VERTICAL as usize];
+            vert[i * n..(i + 1) * n].copy_from_slice(&WEDGE_MASTER_VERTICAL);
+            vert[(i + 1) * n..(i + 2) * n].copy_from_slice(&WEDGE_MASTER_VERTICAL);
+        }
+        for i in 0..n {
+            for j in 0..n {
+                let msk = m[0][WEDGE_OBLIQUE63 as usize][i * n + j];
+                m[0][WEDGE_OBLIQUE27 as usize][j * n + i] = msk;
+                m[0][WEDGE_OBLIQUE117 as usize][i * n + n - 1 - j] = 64 - msk;
+                m[0][WEDGE_OBLIQUE153 as usize][(n - 1 - j) * n + i] = 64 - msk;
+                let mskx = m[0][WEDGE_VERTICAL as usize][i * n + j];
+                m[0][WEDGE_HORIZONTAL as usize][j * n + i] = mskx;
+            }
+        }
+        m[1] = std::array::from_fn(|dir| m[0][dir].iter().map(|&v| 64 - v).collect());
+        m
+    })
+}
+
+/// Wedge mask of a block, `w` samples per row
+fn wedge_mask(bsize: usize, sign: bool, index: usize) -> Vec<u8> {
+    let (w, h) = (block_width(bsize), block_height(bsize));
+    let shape = match w.cmp(&h) {
+        std::cmp::Ordering::Less => 0,
+        std::cmp::Ordering::Greater => 1,
+        std::cmp::Ordering::Equal => 2,
+    };
+    let master = wedge_master();
+    let n = MASK_MASTER_SIZE;
+    let lookup = |index: usize, neg: usize| {
+        let [dir, xoff, yoff] = WEDGE_CODEBOOK[shape][index];
+        let woff = (xoff as usize * w) >> 3;
+        let hoff = (yoff as usize * h) >> 3;
+        let m = &master[neg][dir as usize];
+        move |i: usize, j: usize| m[(n / 2 - hoff + i) * n + n / 2 - woff + j]
+    };
+    // The sign is relative to the side the master mask favours on the
+    // block's top and left edges
+    let base = lookup(index, 0);
+    let border: u32 = (0..w).map(|j| base(0, j) as u32).chain((1..h).map(|i| base(i, 0) as u32)).sum();
+    let count = (w + h - 1) as u32;
+    let flip = (border + (count - 1) / 2) / count < 32;
+    let m = lookup(index, (sign ^ flip) as usize);
+    (0..h).flat_map(|i| (0..w).map(move |j| (i, j))).map(|(i, j)| m(i, j)).collect()
+}
+
+/// Inter-intra smooth mask of a `w`x`h` block
+fn intra_variant_mask(mode: u8, w: usize, h: usize) -> Vec<u8> {
+    let scale = 128 / w.max(h);
+    (0..h)
+        .flat_map(|i| (0..w).map(move |j| (i, j)))
+        .map(|(i, j)| match mode {
+            II_V_PRED => II_WEIGHTS_1D[i * scale],
+            II_H_PRED => II_WEIGHTS_1D[j * scale],
+            II_DC_PRED => 32,
+            _ => II_WEIGHTS_1D[i.min(j) * scale],
+        })
+        .collect()
+}
+
+/// Samples of a reference plane, clamped to the valid area
+struct RefPlane<'a> {
+    buf: &'a PlaneBuf,
+    min_x: i32,
+    min_y: i32,
+    last_x: i32,
+    last_y: i32,
+}
+
+impl RefPlane<'_> {
+    fn at(&self, y: i32, x: i32) -> i32 {
+        let y = y.clamp(self.min_y, self.last_y) as usize;
+        let x = x.clamp(self.min_x, self.last_x) as usize;
+        self.buf.data[(y - self.buf.row0) * self.buf.cols + x - self.buf.col0] as i32
+    }
+}
+
+/// Subpel filtering of a block from a reference plane, starting at
+/// `pos` = (x, y, x step, y step) in 1/1024 samples
+fn block_inter_prediction(src: &RefPlane, pos: [i32; 4], w: usize, h: usize, filter: [u8; 2], r0: u32, r1: u32) -> Vec<i32> {
+    let [start_x, start_y, x_step, y_step] = pos;
+    let inter_h = ((((h as i32 - 1) * y_step + (1 << SCALE_SUBPEL_BITS) - 1) >> SCALE_SUBPEL_BITS) + 8) as usize;
+    let fx = &SUBPEL_FILTERS[filter_index(filter[1], w)];
+    let fy = &SUBPEL_FILTERS[filter_index(filter[0], h)];
+    let mut inter = vec![0i32; inter_h * w];
+    for r in 0..inter_h {
+        let ry = (start_y >> 10) + r as i32 - 3;
+        for c in 0..w {
+            let p = start_x + x_step * c as i32;
+            let taps = &fx[((p >> 6) & 15) as usize];
+            let x0 = (p >> 10) - 3;
+            let s: i32 = taps.iter().enumerate().map(|(t, &k)| k as i32 * src.at(ry, x0 + t as i32)).sum();
+            inter[r * w + c] = round2(s, r0);
+        }
+    }
+    let mut pred = vec![0i32; w * h];
+    for r in 0..h {
+        let p = (start_y & 1023) + y_step * r as i32;
+        let taps = &fy[((p >> 6) & 15) as usize];
+        let base = (p >> 10) as usize;
+        for c in 0..w {
+            let s: i32 = taps.iter().enumerate().map(|(t, &k)| k as i32 * inter[(base + t) * w + c]).sum();
+            pred[r * w + c] = round2(s, r1);
+        }
+    }
+    pred
+}
+
+/// Warp one 8x8 block (`i8`, `j8`) of a `w`x`h` prediction at plane sample
+/// (`x`, `y`) into `pred`
+#[allow(clippy::too_many_arguments)]
+fn block_warp(
+    src: &RefPlane,
+    params: &[i32; 6],
+    shear: [i32; 4],
+    sub: (usize, usize),
+    x: usize,
+    y: usize,
+    i8: usize,
+    j8: usize,
+    w: usize,
+    h: usize,
+    rounds: (u32, u32),
+    pred: &mut [i32],
+) {
+    let (sx, sy) = sub;
+    let [alpha, beta, gamma, delta] = shear;
+    let src_x = ((x + j8 * 8 + 4) << sx) as i64;
+    let src_y = ((y + i8 * 8 + 4) << sy) as i64;
+    let dst_x = params[2] as i64 * src_x + params[3] as i64 * src_y + params[0] as i64;
+    let dst_y = params[4] as i64 * src_x + params[5] as i64 * src_y + params[1] as i64;
+    let x4 = dst_x >> sx;
+    let y4 = dst_y >> sy;
+    let mask = (1i64 << WARPEDMODEL_PREC_BITS) - 1;
+    let (ix4, iy4) = ((x4 >> WARPEDMODEL_PREC_BITS) as i32, (y4 >> WARPEDMODEL_PREC_BITS) as i32);
+    let reduce = !((1i32 << WARP_PARAM_REDUCE_BITS) - 1);
+    let sx4 = ((x4 & mask) as i32 - 4 * alpha - 4 * beta) & reduce;
+    let sy4 = ((y4 & mask) as i32 - 4 * gamma - 4 * delta) & reduce;
+    let filter = |pos: i32| &WARPED_FILTERS[(round2(pos, WARPEDDIFF_PREC_BITS) + WARPEDPIXEL_PREC_SHIFTS) as usize];
+    let mut inter = [[0i32; 8]; 15];
+    for (k, row) in inter.iter_mut().enumerate() {
+        let ry = iy4 + k as i32 - 7;
+        for (l, v) in row.iter_mut().enumerate() {
+            let taps = filter(sx4 + beta * (k as i32 - 3) + alpha * l as i32);
+            let x0 = ix4 + l as i32 - 7;
+            let s: i32 = taps.iter().enumerate().map(|(t, &c)| c as i32 * src.at(ry, x0 + t as i32)).sum();
+            *v = round2(s, rounds.0);
+        }
+    }
+    for k in 0..8.min(h - i8 * 8) {
+        for l in 0..8.min(w - j8 * 8) {
+            let taps = filter(sy4 + delta * k as i32 + gamma * l as i32);
+            let s: i32 = taps.iter().enumerate().map(|(t, &c)| c as i32 * inter[k + t][l]).sum();
+            pred[(i8 * 8 + k) * w + j8 * 8 + l] = round2(s, rounds.1);
+        }
+    }
+}
+
+impl TileDecoder<'_> {
+    /// Predict every plane of an inter block
+    pub(super) fn compute_prediction(&mut self) {
+        let f = self.f;
+        if !self.b.is_inter {
+            return;
+        }
+        if self.b.motion_mode == LOCALWARP {
+            self.warp_estimation();
+        }
+        let b = &self.b;
+        let (mi_row, mi_col, mi_size) = (b.mi_row, b.mi_col, b.mi_size);
+        let sb_mask = if f.seq.use_128x128_superblock { 31 } else { 15 };
+        let (sub_row, sub_col) = ((mi_row & sb_mask) as isize, (mi_col & sb_mask) as isize);
+        let is_inter_intra = b.ref_frame[1] == INTRA_FRAME as i8;
+        let mut mask = Vec::new();
+        for plane in 0..1 + 2 * self.b.has_chroma as usize {
+            let plane_size = if plane == 0 { mi_size } else { subsampled_size(mi_size, f.ss_x, f.ss_y) };
+            let (num4x4_w, num4x4_h) = (block_width4(plane_size), block_height4(plane_size));
+            let (sx, sy) = f.sub(plane);
+            let base_x = (mi_col >> sx) * 4;
+            let base_y = (mi_row >> sy) * 4;
+            if is_inter_intra {
+                let b = &self.b;
+                let mode = match b.interintra_mode {
+                    II_DC_PRED => DC_PRED,
+                    II_V_PRED => V_PRED,
+                    II_H_PRED => H_PRED,
+                    _ => SMOOTH_PRED,
+                };
+                let have_left = if plane == 0 { b.avail_l } else { b.avail_l_chroma };
+                let have_above = if plane == 0 { b.avail_u } else { b.avail_u_chroma };
+                let above_right = self.block_decoded(plane, (sub_row >> sy) - 1, (sub_col >> sx) + num4x4_w as isize);
+                let below_left = self.block_decoded(plane, (sub_row >> sy) + num4x4_h as isize, (sub_col >> sx) - 1);
+                let (log2w, log2h) = (BLOCK_WIDTH_LOG2[plane_size], BLOCK_HEIGHT_LOG2[plane_size]);
+                self.predict_intra(plane, base_x, base_y, have_left, have_above, above_right, below_left, mode, log2w, log2h);
+            }
+            // Chroma of blocks narrower than the subsampled unit combines the
+            // predictions of every covered luma block
+            let mut cand_row = (mi_row >> sy) << sy;
+            let mut cand_col = (mi_col >> sx) << sx;
+            let mut pred_w = block_width(mi_size) >> sx;
+            let mut pred_h = block_height(mi_size) >> sy;
+            let some_use_intra = (0..num4x4_h << sy)
+                .any(|r| (0..num4x4_w << sx).any(|c| self.mi(cand_row + r, cand_col + c).ref_frame[0] <= INTRA_FRAME as i8));
+            if some_use_intra {
+                (pred_w, pred_h, cand_row, cand_col) = (num4x4_w * 4, num4x4_h * 4, mi_row, mi_col);
+            }
+            for (r, y) in (0..num4x4_h * 4).step_by(pred_h).enumerate() {
+                for (c, x) in (0..num4x4_w * 4).step_by(pred_w).enumerate() {
+                    self.predict_inter(plane, base_x + x, base_y + y, pred_w, pred_h, cand_row + r, cand_col + c, &mut mask);
+                }
+            }
+        }
+    }
+
+    /// Reference plane of a reference frame; intra block copy reads the
+    /// current tile
+    fn ref_plane(&self, ref_frame: i8, plane: usize) -> RefPlane<'_> {
+        let f = self.f;
+        let (sx, sy) = f.sub(plane);
+        if ref_frame <= INTRA_FRAME as i8 {
+            let buf = &self.out.planes[plane];
+            let last_x = (((f.hdr.upscaled_width as usize + sx) >> sx) - 1).min(buf.col0 + buf.cols - 1);
+            let last_y = (((f.hdr.frame_height as usize + sy) >> sy) - 1).min(buf.row0 + buf.rows - 1);
+            return RefPlane { buf, min_x: buf.col0 as i32, min_y: buf.row0 as i32, last_x: last_x as i32, last_y: last_y as i32 };
+        }
+        let r = f.refs[ref_frame as usize].as_ref().expect("inter prediction from a missing reference");
+        let last_x = ((r.header.upscaled_width as usize + sx) >> sx) - 1;
+        let last_y = ((r.header.frame_height as usize + sy) >> sy) - 1;
+        RefPlane { buf: &r.planes[plane], min_x: 0, min_y: 0, last_x: last_x as i32, last_y: last_y as i32 }
+    }
+
+    /// Position and step of a block in a reference, in 1/1024 samples
+    fn motion_vector_scaling(&self, plane: usize, ref_frame: i8, x: usize, y: usize, mv: Mv) -> [i32; 4] {
+        let f = self.f;
+        let hdr = f.hdr;
+        let (sx, sy) = f.sub(plane);
+        let (ref_w, ref_h) = match &f.refs.get(ref_frame.max(0) as usize).and_then(Option::as_ref) {
+            Some(r) if ref_frame > INTRA_FRAME as i8 => (r.header.upscaled_width as i64, r.header.frame_height as i64),
+            _ => (hdr.frame_width as i64, hdr.frame_height as i64),
+        };
+        let (fw, fh) = (hdr.frame_width as i64, hdr.frame_height as i64);
+        let x_scale = ((ref_w << REF_SCALE_SHIFT) + fw / 2) / fw;
+        let y_scale = ((ref_h << REF_SCALE_SHIFT) + fh / 2) / fh;
+        let half = 1i64 << (SUBPEL_BITS - 1);
+        let orig_x = ((x as i64) << SUBPEL_BITS) + ((2 * mv[1] as i64) >> sx) + half;
+        let orig_y = ((y as i64) << SUBPEL_BITS) + ((2 * mv[0] as i64) >> sy) + half;
+        let base_x = orig_x * x_scale - (half << REF_SCALE_SHIFT);
+        let base_y = orig_y * y_scale - (half << REF_SCALE_SHIFT);
+        let off = (1i64 << (SCALE_SUBPEL_BITS - SUBPEL_BITS)) / 2;
+        let shift = REF_SCALE_SHIFT + SUBPEL_BITS - SCALE_SUBPEL_BITS;
+        [
+            (round2_signed(base_x, shift) + off) as i32,
+            (round2_signed(base_y, shift) + off) as i32,
+            round2_signed(x_scale, REF_SCALE_SHIFT - SCALE_SUBPEL_BITS) as i32,
+            round2_signed(y_scale, REF_SCALE_SHIFT - SCALE_SUBPEL_BITS) as i32,
+        ]
+    }
+
+    /// Predict a `w`x`h` area with the motion of the block at
+    /// (`cand_row`, `cand_col`)
+    #[allow(clippy::too_many_arguments)]
+    fn predict_inter(&mut self, plane: usize, x: usize, y: usize, w: usize, h: usize, cand_row: usize, cand_col: usize, mask: &mut Vec<u8>) {
+        let f = self.f;
+        let hdr = f.hdr;
+        let cand = *self.mi(cand_row, cand_col);
+        let is_compound = cand.ref_frame[1] > INTRA_FRAME as i8;
+        let (r0, r1, post) = rounding(f.bit_depth, is_compound);
+        let b = &self.b;
+        let global_mode = b.y_mode == GLOBALMV || b.y_mode == GLOBAL_GLOBALMV;
+        let mut preds: [Vec<i32>; 2] = [Vec::new(), Vec::new()];
+        for (list, pred) in preds.iter_mut().enumerate().take(1 + is_compound as usize) {
+            let rf = cand.ref_frame[list];
+            let src = self.ref_plane(rf, plane);
+            let gm = &hdr.gm_params[rf.max(0) as usize];
+            let warp = if w < 8 || h < 8 || hdr.force_integer_mv {
+                None
+            } else if b.motion_mode == LOCALWARP && b.local_valid {
+                setup_shear(&b.local_warp_params).map(|s| (b.local_warp_params, s))
+            } else if global_mode && hdr.gm_type[rf.max(0) as usize] > TRANSLATION && !self.is_scaled(rf) {
+                setup_shear(gm).map(|s| (*gm, s))
+            } else {
+                None
+            };
+            if let Some((params, shear)) = warp {
+                *pred = vec![0; w * h];
+                for i8 in 0..=(h - 1) >> 3 {
+                    for j8 in 0..=(w - 1) >> 3 {
+                        block_warp(&src, &params, shear, f.sub(plane), x, y, i8, j8, w, h, (r0, r1), pred);
+                    }
+                }
+            } else {
+                let pos = self.motion_vector_scaling(plane, rf, x, y, cand.mv[list]);
+                *pred = block_inter_prediction(&src, pos, w, h, cand.interp_filter, r0, r1);
+            }
+        }
+
+        let b = &self.b;
+        let is_inter_intra = b.ref_frame[1] == INTRA_FRAME as i8;
+        if is_compound && b.compound_type == COMPOUND_WEDGE && plane == 0 {
+            *mask = wedge_mask(b.mi_size, b.wedge_sign, b.wedge_index);
+        } else if is_inter_intra {
+            *mask = if b.wedge_interintra {
+                wedge_mask(b.mi_size, false, b.wedge_index)
+            } else {
+                intra_variant_mask(b.interintra_mode, w, h)
+            };
+        } else if is_compound && b.compound_type == COMPOUND_DIFFWTD && plane == 0 {
+            let round = (f.bit_depth as u32 - 8) + post;
+            *mask = preds[0]
+                .iter()
+                .zip(&preds[1])
+                .map(|(&p0, &p1)| {
+                    let m = (38 + round2((p0 - p1).abs(), round) / 16).clamp(0, 64) as u8;
+                    if b.mask_type { 64 - m } else { m }
+                })
+                .collect();
+        }
+
+        let max = (1i32 << f.bit_depth) - 1;
+        let dst = &mut self.out.planes[plane];
+        if !is_compound && !is_inter_intra {
+            for i in 0..h {
+                for j in 0..w {
+                    *dst.get_mut(y + i, x + j) = round2(preds[0][i * w + j], post).clamp(0, max) as u16;
+                }
+            }
+        } else if is_compound && b.compound_type == COMPOUND_DISTANCE {
+            let (fwd, bck) = self.distance_weights(&cand);
+            let dst = &mut self.out.planes[plane];
+            for i in 0..h {
+                for j in 0..w {
+                    let k = i * w + j;
+                    *dst.get_mut(y + i, x + j) = round2(fwd * preds[0][k] + bck * preds[1][k], 4 + post).clamp(0, max) as u16;
+                }
+            }
+        } else if is_compound && b.compound_type != COMPOUND_WEDGE && b.compound_type != COMPOUND_DIFFWTD {
+            for i in 0..h {
+                for j in 0..w {
+                    let k = i * w + j;
+                    *dst.get_mut(y + i, x + j) = round2(preds[0][k] + preds[1][k], 1 + post).clamp(0, max) as u16;
+                }
+            }
+        } else {
+            self.mask_blend(&preds, mask, plane, x, y, w, h, post);
+        }
+        if self.b.motion_mode == OBMC {
+            self.overlapped_motion_compensation(plane, w, h);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn mask_blend(&mut self, preds: &[Vec<i32>; 2], mask: &[u8], plane: usize, x: usize, y: usize, w: usize, h: usize, post: u32) {
+        let f = self.f;
+        let b = &self.b;
+        let (sx, sy) = f.sub(plane);
+        let is_inter_intra = b.ref_frame[1] == INTRA_FRAME as i8;
+        let plane_mask = is_inter_intra && !b.wedge_interintra;
+        let stride = if plane_mask { w } else { block_width(b.mi_size) };
+        let m_at = |i: usize, j: usize| mask[i * stride + j] as i32;
+        let max = (1i32 << f.bit_depth) - 1;
+        let dst = &mut self.out.planes[plane];
+        for i in 0..h {
+            for j in 0..w {
+                let m = match (plane_mask, sx, sy) {
+                    (true, _, _) | (_, 0, 0) => m_at(i, j),
+                    (_, 1, 0) => round2(m_at(i, 2 * j) + m_at(i, 2 * j + 1), 1),
+                    (_, 0, 1) => round2(m_at(2 * i, j) + m_at(2 * i + 1, j), 1),
+                    _ => round2(m_at(2 * i, 2 * j) + m_at(2 * i, 2 * j + 1) + m_at(2 * i + 1, 2 * j) + m_at(2 * i + 1, 2 * j + 1), 2),
+                };
+                let k = i * w + j;
+                let px = dst.get_mut(y + i, x + j);
+                *px = if is_inter_intra {
+                    let inter = round2(preds[0][k], post).clamp(0, max);
+                    round2(m * *px as i32 + (64 - m) * inter, 6)
+                } else {
+                    round2(m * preds[0][k] + (64 - m) * preds[1][k], 6 + post).clamp(0, max)
+                } as u16;
+            }
+        }
+    }
+
+    /// Compound weights from the distances to the two references
+    fn distance_weights(&self, cand: &MiInfo) -> (i32, i32) {
+        let f = self.f;
+        let hdr = f.hdr;
+        let dist = |list: usize| {
+            let hint = hdr.order_hints[cand.ref_frame[list] as usize];
+            FrameHeader::relative_dist(f.seq, hint, hdr.order_hint).abs().clamp(0, MAX_FRAME_DISTANCE)
+        };
+        let (d0, d1) = (dist(1), dist(0));
+        let order = (d0 <= d1) as usize;
+        if d0 == 0 || d1 == 0 {
+            return (QUANT_DIST_LOOKUP[3][order], QUANT_DIST_LOOKUP[3][1 - order]);
+        }
+        let i = (0..3)
+            .find(|&i| {
+                let (c0, c1) = (QUANT_DIST_WEIGHT[i][order], QUANT_DIST_WEIGHT[i][1 - order]);
+                if order == 1 { d0 * c0 > d1 * c1 } else { d0 * c0 < d1 * c1 }
+            })
+            .unwrap_or(3);
+        (QUANT_DIST_LOOKUP[i][order], QUANT_DIST_LOOKUP[i][1 - order])
+    }
+
+    /// Blend the predictions of the above and left neighbours into the
+    /// edges of the block
+    fn overlapped_motion_compensation(&mut self, plane: usize, w: usize, h: usize) {
+        let f = self.f;
+        let hdr = f.hdr;
+        let b = &self.b;
+        let (mi_row, mi_col, mi_size) = (b.mi_row, b.mi_col, b.mi_size);
+        let (bw4, bh4, avail_u, avail_l) = (b.bw4, b.bh4, b.avail_u, b.avail_l);
+        let (sx, sy) = f.sub(plane);
+        let plane_size = if plane == 0 { mi_size } else { subsampled_size(mi_size, f.ss_x, f.ss_y) };
+        if plane_size < BLOCK_8X8 {
+            return;
+        }
+        if avail_u {
+            let limit = 4.min(BLOCK_WIDTH_LOG2[mi_size] as usize - 2);
+            let (mut x4, mut count) = (mi_col, 0);
+            while count < limit && x4 < hdr.mi_cols.min(mi_col + bw4) {
+                let cand = *self.mi(mi_row - 1, x4 | 1);
+                let step4 = block_width4(cand.mi_size as usize).clamp(2, 16);
+                if cand.ref_frame[0] > INTRA_FRAME as i8 {
+                    count += 1;
+                    let pred_w = w.min((step4 * 4) >> sx);
+                    let pred_h = (h >> 1).min(32 >> sy);
+                    self.predict_overlap(plane, &cand, x4, mi_row, pred_w, pred_h, true);
+                }
+                x4 += step4;
+            }
+        }
+        if avail_l {
+            let limit = 4.min(BLOCK_HEIGHT_LOG2[mi_size] as usize - 2);
+            let (mut y4, mut count) = (mi_row, 0);
+            while count < limit && y4 < hdr.mi_rows.min(mi_row + bh4) {
+                let cand = *self.mi(y4 | 1, mi_col - 1);
+                let step4 = block_height4(cand.mi_size as usize).clamp(2, 16);
+                if cand.ref_frame[0] > INTRA_FRAME as i8 {
+                    count += 1;
+                    let pred_w = (w >> 1).min(32 >> sx);
+                    let pred_h = h.min((step4 * 4) >> sy);
+                    self.predict_overlap(plane, &cand, mi_col, y4, pred_w, pred_h, false);
+                }
+                y4 += step4;
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn predict_overlap(&mut self, plane: usize, cand: &MiInfo, x4: usize, y4: usize, w: usize, h: usize, above: bool) {
+        let f = self.f;
+        let (sx, sy) = f.sub(plane);
+        let (pred_x, pred_y) = ((x4 * 4) >> sx, (y4 * 4) >> sy);
+        let (r0, r1, _) = rounding(f.bit_depth, false);
+        let pos = self.motion_vector_scaling(plane, cand.ref_frame[0], pred_x, pred_y, cand.mv[0]);
+        let pred = block_inter_prediction(&self.ref_plane(cand.ref_frame[0], plane), pos, w, h, cand.interp_filter, r0, r1);
+        let mask = obmc_mask(if above { h } else { w });
+        let max = (1i32 << f.bit_depth) - 1;
+        let dst = &mut self.out.planes[plane];
+        for i in 0..h {
+            for j in 0..w {
+                let m = mask[if above { i } else { j }] as i32;
+                let px = dst.get_mut(pred_y + i, pred_x + j);
+                *px = round2(m * *px as i32 + (64 - m) * pred[i * w + j].clamp(0, max), 6) as u16;
+            }
+        }
+    }
+
+    /// Fit an affine model to the motion of the neighbouring blocks
+    fn warp_estimation(&mut self) {
+        let b = &mut self.b;
+        let (mut a, mut bx, mut by) = ([[0i64; 2]; 2], [0i64; 2], [0i64; 2]);
+        let mid_y = (b.mi_row * 4 + b.bh4 * 2) as i32 - 1;
+        let mid_x = (b.mi_col * 4 + b.bw4 * 2) as i32 - 1;
+        let (suy, sux) = (mid_y * 8, mid_x * 8);
+        let (duy, dux) = (suy + b.mv[0][0], sux + b.mv[0][1]);
+        let ls_product = |a: i32, b: i32| (((a * b) >> 2) + (a + b)) as i64;
+        for cand in &b.cand_list[..b.num_samples] {
+            let (sy, sx) = (cand[0] - suy, cand[1] - sux);
+            let (dy, dx) = (cand[2] - duy, cand[3] - dux);
+            if (sx - dx).abs() < LS_MV_MAX && (sy - dy).abs() < LS_MV_MAX {
+                a[0][0] += ls_product(sx, sx) + 8;
+                a[0][1] += ls_product(sx, sy) + 4;
+                a[1][1] += ls_product(sy, sy) + 8;
+                bx[0] += ls_product(sx, dx) + 8;
+                bx[1] += ls_product(sy, dx) + 4;
+                by[0] += ls_product(sx, dy) + 4;
+                by[1] += ls_product(sy, dy) + 8;
+            }
+        }
+        let det = a[0][0] * a[1][1] - a[0][1] * a[0][1];
+        b.local_valid = det != 0;
+        if !b.local_valid {
+            return;
+        }
+        let (shift, mut factor) = resolve_divisor(det);
+        let mut shift = shift as i32 - WARPEDMODEL_PREC_BITS as i32;
+        if shift < 0 {
+            factor <<= -shift;
+            shift = 0;
+        }
+        let one = 1i64 << WARPEDMODEL_PREC_BITS;
+        let div = |v: i64| round2_signed(v * factor, shift as u32);
+        let diag = |v: i64| div(v).clamp(one - WARPEDMODEL_NONDIAGAFFINE_CLAMP + 1, one + WARPEDMODEL_NONDIAGAFFINE_CLAMP - 1);
+        let nondiag = |v: i64| div(v).clamp(-WARPEDMODEL_NONDIAGAFFINE_CLAMP + 1, WARPEDMODEL_NONDIAGAFFINE_CLAMP - 1);
+        let p2 = diag(a[1][1] * bx[0] - a[0][1] * bx[1]);
+        let p3 = nondiag(-a[0][1] * bx[0] + a[0][0] * bx[1]);
+        let p4 = nondiag(a[1][1] * by[0] - a[0][1] * by[1]);
+        let p5 = diag(-a[0][1] * by[0] + a[0][0] * by[1]);
+        let (mid_x, mid_y) = (mid_x as i64, mid_y as i64);
+        let vx = b.mv[0][1] as i64 * (1 << (WARPEDMODEL_PREC_BITS - 3)) - (mid_x * (p2 - one) + mid_y * p3);
+        let vy = b.mv[0][0] as i64 * (1 << (WARPEDMODEL_PREC_BITS - 3)) - (mid_x * p4 + mid_y * (p5 - one));
+        let clamp = |v: i64| v.clamp(-WARPEDMODEL_TRANS_CLAMP, WARPEDMODEL_TRANS_CLAMP - 1) as i32;
+        b.local_warp_params = [clamp(vx), clamp(vy), p2 as i32, p3 as i32, p4 as i32, p5 as i32];
+        b.local_valid = setup_shear(&b.local_warp_params).is_some();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wedge_masks_are_complementary() {
+        let a = wedge_mask(BLOCK_16X16, false, 5);
+        let b = wedge_mask(BLOCK_16X16, true, 5);
+        assert_eq!(a.len(), 256);
+        assert!(a.iter().zip(&b).all(|(&x, &y)| x + y == 64));
+        // Either side of the wedge is predicted from one of the references
+        assert!(a.contains(&0) && a.contains(&64));
+    }
+
+    #[test]
+    fn test_identity_shear() {
+        let identity = [0, 0, 1 << 16, 0, 0, 1 << 16];
+        assert_eq!(setup_shear(&identity), Some([0, 0, 0, 0]));
+        assert_eq!(filter_index(2, 4), 4);
+        assert_eq!(filter_index(1, 8), 1);
+    }
+}
@@ -0,0 +1,473 @@
+//! AV1 Intra Prediction
+//!
+//! Directional predictors with edge smoothing and upsampling, smooth, DC
+//! and Paeth predictors, recursive filter intra, palettes and chroma from
+//! luma. Predictions are written straight into the tile's sample planes.
+
+use super::tables::*;
+use super::tile::{is_directional_mode, TileDecoder};
+
+const ANGLE_STEP: i32 = 3;
+/// Position of AboveRow[0] and LeftCol[0] in the edge buffers, leaving room
+/// for the corner and upsampled samples before them
+const EDGE_ORIGIN: usize = 16;
+
+fn round2(x: i32, n: u32) -> i32 {
+    (x + (1 << (n - 1))) >> n
+}
+
+fn round2_signed(x: i32, n: u32) -> i32 {
+    if x >= 0 { round2(x, n) } else { -round2(-x, n) }
+}
+
+fn edge_filter_strength(w: usize, h: usize, smooth: bool, delta: i32) -> usize {
+    let d = delta.abs();
+    let wh = w + h;
+    if smooth {
+        match wh {
+            0..=8 => (d >= 40) as usize + (d >= 64) as usize,
+            9..=16 => (d >= 20) as usize + (d >= 48) as usize,
+            17..=24 => if d >= 4 { 3 } else { 0 },
+            _ => if d >= 1 { 3 } else { 0 },
+        }
+    } else {
+        match wh {
+            0..=8 => (d >= 56) as usize,
+            9..=16 => (d >= 40) as usize,
+            17..=24 => (d >= 8) as usize + (d >= 16) as usize + (d >= 32) as usize,
+            25..=32 => (d >= 1) as usize + (d >= 4) as usize + (d >= 32) as usize,
+            _ => if d >= 1 { 3 } else { 0 },
+        }
+    }
+}
+
+fn use_upsample(w: usize, h: usize, smooth: bool, delta: i32) -> bool {
+    let d = delta.abs();
+    d > 0 && d < 40 && w + h <= if smooth { 8 } else { 16 }
+}
+
+/// Smooth `size` edge samples starting at the corner sample `start`
+fn filter_edge(edge: &mut [i32], start: usize, size: usize, strength: usize) {
+    if strength == 0 {
+        return;
+    }
+    let src: Vec<i32> = edge[start..start + size].to_vec();
+    for i in 1..size {
+        let s: i32 = (0..5)
+            .map(|j| INTRA_EDGE_KERNEL[strength - 1][j] * src[(i + j).saturating_sub(2).min(size - 1)])
+            .sum();
+        edge[start + i] = (s + 8) >> 4;
+    }
+}
+
+/// Double the resolution of `size` edge samples; the corner moves from
+/// index -1 to -2
+fn upsample_edge(edge: &mut [i32], size: usize, bit_depth: u8) {
+    let o = EDGE_ORIGIN;
+    let mut dup = vec![0i32; size + 3];
+    dup[0] = edge[o - 1];
+    dup[1..size + 2].copy_from_slice(&edge[o - 1..o + size]);
+    dup[size + 2] = edge[o + size - 1];
+    let max = (1 << bit_depth) - 1;
+    edge[o - 2] = dup[0];
+    for i in 0..size {
+        let s = -dup[i] + 9 * dup[i + 1] + 9 * dup[i + 2] - dup[i + 3];
+        edge[o + 2 * i - 1] = round2(s, 4).clamp(0, max);
+        edge[o + 2 * i] = dup[i + 2];
+    }
+}
+
+impl TileDecoder<'_> {
+    /// Predict one intra transform block at sample (`x`, `y`) of `plane`
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn predict_intra(
+        &mut self,
+        plane: usize,
+        x: usize,
+        y: usize,
+        have_left: bool,
+        have_above: bool,
+        have_above_right: bool,
+        have_below_left: bool,
+        mode: u8,
+        log2w: u8,
+        log2h: u8,
+    ) {
+        let f = self.f;
+        let (w, h) = (1usize << log2w, 1usize << log2h);
+        let (sx, sy) = f.sub(plane);
+        let max_x = ((f.hdr.mi_cols * 4) >> sx) - 1;
+        let max_y = ((f.hdr.mi_rows * 4) >> sy) - 1;
+        let bd = f.bit_depth;
+        let mid = 1i32 << (bd - 1);
+        let buf = &self.out.planes[plane];
+        let px = |row: usize, col: usize| *buf.get(row, col) as i32;
+        let n = w + h;
+        let o = EDGE_ORIGIN;
+        let mut above = vec![0i32; 2 * n + 2 * o];
+        let mut left = vec![0i32; 2 * n + 2 * o];
+        let above_limit = max_x.min(x + if have_above_right { 2 * w } else { w } - 1);
+        let left_limit = max_y.min(y + if have_below_left { 2 * h } else { h } - 1);
+        for i in 0..n {
+            above[o + i] = match (have_above, have_left) {
+                (false, true) => px(y, x - 1),
+                (false, false) => mid - 1,
+                _ => px(y - 1, above_limit.min(x + i)),
+            };
+            left[o + i] = match (have_left, have_above) {
+                (false, true) => px(y - 1, x),
+                (false, false) => mid + 1,
+                _ => px(left_limit.min(y + i), x - 1),
+            };
+        }
+        let corner = match (have_above, have_left) {
+            (true, true) => px(y - 1, x - 1),
+            (true, false) => px(y - 1, x),
+            (false, true) => px(y, x - 1),
+            _ => mid,
+        };
+        above[o - 1] = corner;
+        left[o - 1] = corner;
+
+        let b = &self.b;
+        let pred = if plane == 0 && b.use_filter_intra {
+            self.filter_intra_pred(&above, &left, w, h)
+        } else if is_directional_mode(mode) {
+            let delta = if plane == 0 { b.angle_delta_y } else { b.angle_delta_uv };
+            let angle = MODE_TO_ANGLE[mode as usize] + delta * ANGLE_STEP;
+            let smooth = self.filter_type(plane);
+            let limits = (max_x + 1 - x, max_y + 1 - y);
+            directional_pred(&mut above, &mut left, w, h, angle, smooth, limits, have_above, have_left, f)
+        } else {
+            match mode {
+                SMOOTH_PRED | SMOOTH_V_PRED | SMOOTH_H_PRED => smooth_pred(&above, &left, w, h, mode),
+                DC_PRED => dc_pred(&above, &left, w, h, have_above, have_left, mid),
+                _ => paeth_pred(&above, &left, w, h),
+            }
+        };
+        let dst = &mut self.out.planes[plane];
+        for i in 0..h {
+            for j in 0..w {
+                *dst.get_mut(y + i, x + j) = pred[i * w + j] as u16;
+            }
+        }
+    }
+
+    /// Whether a neighbour uses a smooth predictor, selecting the stronger
+    /// edge filters
+    fn filter_type(&self, plane: usize) -> bool {
+        let b = &self.b;
+        let (ss_x, ss_y) = (self.f.ss_x, self.f.ss_y);
+        let is_smooth = |row: usize, col: usize| {
+            let mi = self.mi(row, col);
+            let mode = if plane == 0 {
+                mi.y_mode
+            } else {
+                if mi.ref_frame[0] > 0 {
+                    return false;
+                }
+                mi.uv_mode
+            };
+            matches!(mode, SMOOTH_PRED | SMOOTH_V_PRED | SMOOTH_H_PRED)
+        };
+        let (avail_u, avail_l) = if plane == 0 { (b.avail_u, b.avail_l) } else { (b.avail_u_chroma, b.avail_l_chroma) };
+        let mut smooth = false;
+        if avail_u {
+            let (mut r, mut c) = (b.mi_row - 1, b.mi_col);
+            if plane > 0 {
+                if ss_x && b.mi_col & 1 == 0 {
+                    c += 1;
+                }
+                if ss_y && b.mi_row & 1 == 1 {
+                    r -= 1;
+                }
+            }
+            smooth |= is_smooth(r, c);
+        }
+        if avail_l {
+            let (mut r, mut c) = (b.mi_row, b.mi_col - 1);
+            if plane > 0 {
+                if ss_x && b.mi_col & 1 == 1 {
+                    c -= 1;
+                }
+                if ss_y && b.mi_row & 1 == 0 {
+                    r += 1;
+                }
+            }
+            smooth |= is_smooth(r, c);
+        }
+        smooth
+    }
+
+    /// Filter intra: each 4x2 cell is predicted from the seven samples above
+    /// and to its left, including already predicted cells
+    fn filter_intra_pred(&self, above: &[i32], left: &[i32], w: usize, h: usize) -> Vec<i32> {
+        let o = EDGE_ORIGIN;
+        let max = (1 << self.f.bit_depth) - 1;
+        let taps = &FILTER_INTRA_TAPS[self.b.filter_intra_mode as usize];
+        let mut pred = vec![0i32; w * h];
+        for i2 in 0..h / 2 {
+            for j4 in 0..w / 4 {
+                let mut p = [0i32; 7];
+                for (i, v) in p.iter_mut().enumerate() {
+                    *v = if i < 5 {
+                        if i2 == 0 {
+                            above[o + 4 * j4 + i - 1]
+                        } else if j4 == 0 && i == 0 {
+                            left[o + 2 * i2 - 1]
+                        } else {
+                            pred[(2 * i2 - 1) * w + 4 * j4 + i - 1]
+                        }
+                    } else if j4 == 0 {
+                        left[o + 2 * i2 + i - 5]
+                    } else {
+                        pred[(2 * i2 + i - 5) * w + 4 * j4 - 1]
+                    };
+                }
+                for (i, tap) in taps.iter().enumerate() {
+                    let pr: i32 = tap.iter().zip(&p).map(|(&t, &v)| t as i32 * v).sum();
+                    pred[(2 * i2 + (i >> 2)) * w + 4 * j4 + (i & 3)] = round2_signed(pr, 4).clamp(0, max);
+                }
+            }
+        }
+        pred
+    }
+
+    /// Palette prediction of a transform block; (`x4`, `y4`) is its offset
+    /// in 4x4 units within the block
+    pub(super) fn predict_palette(&mut self, plane: usize, start_x: usize, start_y: usize, x4: usize, y4: usize, tx: usize) {
+        let b = &self.b;
+        let (w, h) = (tx_width(tx), tx_height(tx));
+        let (map, stride) = if plane == 0 {
+            (&b.color_map_y, block_width(b.mi_size))
+        } else {
+            let bw = block_width(b.mi_size) >> self.f.ss_x as usize;
+            (&b.color_map_uv, if bw < 4 { bw + 2 } else { bw })
+        };
+        let palette = &b.palette_colors[plane];
+        let dst = &mut self.out.planes[plane];
+        for i in 0..h {
+            for j in 0..w {
+                *dst.get_mut(start_y + i, start_x + j) = palette[map[(y4 * 4 + i) * stride + x4 * 4 + j] as usize];
+            }
+        }
+    }
+
+    /// Add the scaled, zero-mean luma of the block to the DC prediction of
+    /// a chroma transform block
+    pub(super) fn predict_chroma_from_luma(&mut self, plane: usize, start_x: usize, start_y: usize, tx: usize) {
+        let b = &self.b;
+        let (w, h) = (tx_width(tx), tx_height(tx));
+        let (sx, sy) = self.f.sub(plane);
+        let alpha = if plane == 1 { b.cfl_alpha_u } else { b.cfl_alpha_v };
+        let luma = &self.out.planes[0];
+        let mut l = vec![0i32; w * h];
+        let mut sum = 0i32;
+        for i in 0..h {
+            let luma_y = ((start_y + i) << sy).min(b.max_luma_h - (1 << sy));
+            for j in 0..w {
+                let luma_x = ((start_x + j) << sx).min(b.max_luma_w - (1 << sx));
+                let mut t = 0i32;
+                for dy in 0..=sy {
+                    for dx in 0..=sx {
+                        t += *luma.get(luma_y + dy, luma_x + dx) as i32;
+                    }
+                }
+                let v = t << (3 - sx - sy);
+                l[i * w + j] = v;
+                sum += v;
+            }
+        }
+        let avg = round2(sum, TX_WIDTH_LOG2[tx] as u32 + TX_HEIGHT_LOG2[tx] as u32);
+        let max = (1 << self.f.bit_depth) - 1;
+        let dst = &mut self.out.planes[plane];
+        for i in 0..h {
+            for j in 0..w {
+                let d = dst.get_mut(start_y + i, start_x + j);
+                let scaled = round2_signed(alpha * (l[i * w + j] - avg), 6);
+                *d = (*d as i32 + scaled).clamp(0, max) as u16;
+            }
+        }
+    }
+}
+
+/// Directional prediction along `angle` degrees; `limits` are the samples
+/// left in the frame to the right of and below the block
+#[allow(clippy::too_many_arguments)]
+fn directional_pred(
+    above: &mut [i32],
+    left: &mut [i32],
+    w: usize,
+    h: usize,
+    angle: i32,
+    smooth: bool,
+    limits: (usize, usize),
+    have_above: bool,
+    have_left: bool,
+    f: &super::tile::FrameContext,
+) -> Vec<i32> {
+    let o = EDGE_ORIGIN;
+    let bd = f.bit_depth;
+    let mut up_above = 0;
+    let mut up_left = 0;
+    if f.seq.enable_intra_edge_filter {
+        if angle != 90 && angle != 180 {
+            if angle > 90 && angle < 180 && w + h >= 24 {
+                let c = round2(left[o] * 5 + above[o - 1] * 6 + above[o] * 5, 4);
+                above[o - 1] = c;
+                left[o - 1] = c;
+            }
+            if have_above {
+                let strength = edge_filter_strength(w, h, smooth, angle - 90);
+                let num = w.min(limits.0) + if angle < 90 { h } else { 0 } + 1;
+                filter_edge(above, o - 1, num, strength);
+            }
+            if have_left {
+                let strength = edge_filter_strength(w, h, smooth, angle - 180);
+                let num = h.min(limits.1) + if angle > 180 { w } else { 0 } + 1;
+                filter_edge(left, o - 1, num, strength);
+            }
+        }
+        if use_upsample(w, h, smooth, angle - 90) {
+            up_above = 1;
+            upsample_edge(above, w + if angle < 90 { h } else { 0 }, bd);
+        }
+        if use_upsample(w, h, smooth, angle - 180) {
+            up_left = 1;
+            upsample_edge(left, h + if angle > 180 { w } else { 0 }, bd);
+        }
+    }
+    let dx = if angle < 90 {
+        dr_intra_derivative(angle)
+    } else if angle > 90 && angle < 180 {
+        dr_intra_derivative(180 - angle)
+    } else {
+        0
+    };
+    let dy = if angle > 90 && angle < 180 {
+        dr_intra_derivative(angle - 90)
+    } else if angle > 180 {
+        dr_intra_derivative(270 - angle)
+    } else {
+        0
+    };
+    let at = |edge: &[i32], i: isize| edge[(o as isize + i) as usize];
+    let interp = |edge: &[i32], base: isize, shift: i32| round2(at(edge, base) * (32 - shift) + at(edge, base + 1) * shift, 5);
+    let mut pred = vec![0i32; w * h];
+    for i in 0..h {
+        for j in 0..w {
+            pred[i * w + j] = if angle < 90 {
+                let idx = (i as i32 + 1) * dx;
+                let base = ((idx >> (6 - up_above)) + ((j as i32) << up_above)) as isize;
+                let shift = ((idx << up_above) >> 1) & 0x1F;
+                let max_base = ((w + h - 1) << up_above) as isize;
+                if base < max_base { interp(above, base, shift) } else { at(above, max_base) }
+            } else if angle > 90 && angle < 180 {
+                let idx = ((j as i32) << 6) - (i as i32 + 1) * dx;
+                let base = (idx >> (6 - up_above)) as isize;
+                if base >= -(1 << up_above) {
+                    interp(above, base, ((idx << up_above) >> 1) & 0x1F)
+                } else {
+                    let idx = ((i as i32) << 6) - (j as i32 + 1) * dy;
+                    let base = (idx >> (6 - up_left)) as isize;
+                    interp(left, base, ((idx << up_left) >> 1) & 0x1F)
+                }
+            } else if angle > 180 {
+                let idx = (j as i32 + 1) * dy;
+                let base = ((idx >> (6 - up_left)) + ((i as i32) << up_left)) as isize;
+                let shift = ((idx << up_left) >> 1) & 0x1F;
+                let max_base = ((w + h - 1) << up_left) as isize;
+                if base < max_base { interp(left, base, shift) } else { at(left, max_base) }
+            } else if angle == 90 {
+                above[o + j]
+            } else {
+                left[o + i]
+            };
+        }
+    }
+    pred
+}
+
+fn smooth_pred(above: &[i32], left: &[i32], w: usize, h: usize, mode: u8) -> Vec<i32> {
+    let o = EDGE_ORIGIN;
+    let (wx, wy) = (sm_weights(w), sm_weights(h));
+    let (right, below) = (above[o + w - 1], left[o + h - 1]);
+    let mut pred = vec![0i32; w * h];
+    for i in 0..h {
+        for j in 0..w {
+            let (a, l) = (above[o + j], left[o + i]);
+            let (x, y) = (wx[j] as i32, wy[i] as i32);
+            pred[i * w + j] = match mode {
+                SMOOTH_PRED => round2(y * a + (256 - y) * below + x * l + (256 - x) * right, 9),
+                SMOOTH_V_PRED => round2(y * a + (256 - y) * below, 8),
+                _ => round2(x * l + (256 - x) * right, 8),
+            };
+        }
+    }
+    pred
+}
+
+fn dc_pred(above: &[i32], left: &[i32], w: usize, h: usize, have_above: bool, have_left: bool, mid: i32) -> Vec<i32> {
+    let o = EDGE_ORIGIN;
+    let sum_above: i32 = above[o..o + w].iter().sum();
+    let sum_left: i32 = left[o..o + h].iter().sum();
+    let avg = match (have_above, have_left) {
+        (true, true) => (sum_above + sum_left + ((w + h) >> 1) as i32) / (w + h) as i32,
+        (false, true) => (sum_left + (h >> 1) as i32) >> h.ilog2(),
+        (true, false) => (sum_above + (w >> 1) as i32) >> w.ilog2(),
+        _ => mid,
+    };
+    vec![avg; w * h]
+}
+
+fn paeth_pred(above: &[i32], left: &[i32], w: usize, h: usize) -> Vec<i32> {
+    let o = EDGE_ORIGIN;
+    let corner = above[o - 1];
+    let mut pred = vec![0i32; w * h];
+    for i in 0..h {
+        for j in 0..w {
+            let (a, l) = (above[o + j], left[o + i]);
+            let base = a + l - corner;
+            let (p_left, p_top, p_corner) = ((base - l).abs(), (base - a).abs(), (base - corner).abs());
+            pred[i * w + j] = if p_left <= p_top && p_left <= p_corner {
+                l
+            } else if p_top <= p_corner {
+                a
+            } else {
+                corner
+            };
+        }
+    }
+    pred
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edge_upsampling() {
+        let mut edge = vec![0i32; 2 * EDGE_ORIGIN + 8];
+        for i in 0..5 {
+            edge[EDGE_ORIGIN - 1 + i] = 100;
+        }
+        upsample_edge(&mut edge, 4, 8);
+        // A flat edge stays flat at twice the resolution
+        assert!(edge[EDGE_ORIGIN - 2..EDGE_ORIGIN + 7].iter().all(|&v| v == 100));
+        assert_eq!(edge_filter_strength(16, 16, false, 3), 1);
+        assert_eq!(edge_filter_strength(16, 16, false, 32), 3);
+        assert!(use_upsample(4, 4, true, 10) && !use_upsample(16, 16, false, 10));
+    }
+
+    #[test]
+    fn test_paeth_and_dc() {
+        let mut above = vec![0i32; 2 * EDGE_ORIGIN + 8];
+        let mut left = above.clone();
+        above[EDGE_ORIGIN - 1] = 10;
+        above[EDGE_ORIGIN..EDGE_ORIGIN + 4].fill(50);
+        left[EDGE_ORIGIN..EDGE_ORIGIN + 4].fill(10);
+        // Horizontal edge: the prediction follows the row above
+        assert!(paeth_pred(&above, &left, 4, 4).iter().all(|&v| v == 50));
+        assert!(dc_pred(&above, &left, 4, 4, true, true, 128).iter().all(|&v| v == 30));
+    }
+}
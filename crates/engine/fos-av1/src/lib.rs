@@ -0,0 +1,503 @@
+//! fOS AV1
+//!
+//! Pure-Rust AV1 decoder shared by video playback and AVIF images. Tiles of
+//! a frame decode in parallel into their own buffers; the frame is then
+//! assembled and run through the deblocking, CDEF, super-resolution and
+//! loop restoration filters before it replaces the reference slots named by
+//! the header. Film grain parameters are parsed and carried along but grain
+//! is not synthesized. Malformed data is reported as an [`Av1Error`], never
+//! by panicking.
+
+mod bits;
+mod cdef;
+mod cdf;
+mod headers;
+mod inter;
+mod intra;
+mod loopfilter;
+mod mvpred;
+mod residual;
+mod restoration;
+mod state;
+mod symbol;
+mod tables;
+mod tile;
+mod transform;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+
+pub use headers::{Av1FrameType, Av1SequenceHeader, ObuType};
+
+use bits::BitReader;
+use cdf::{CdfArray, CdfContext, CoeffCdfs};
+use headers::{parse_obus, Av1Bits, FrameHeader, RefHeaders, INTRA_FRAME, KEY_FRAME, LAST_FRAME, NUM_REF_FRAMES, PRIMARY_REF_NONE};
+use mvpred::{MotionField, REFMVS_LIMIT};
+use state::{Grid, MiInfo, Mv, PlaneBuf, RefFrame};
+use std::sync::Arc;
+use tables::block_width4;
+use tile::{count_units_in_frame, FrameContext, LrUnit, TileDecoder, TileOutput};
+
+/// AV1 decoding error
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum Av1Error {
+    #[error("Invalid bitstream: {0}")]
+    InvalidBitstream(String),
+
+    #[error("Unsupported feature: {0}")]
+    Unsupported(String),
+
+    #[error("Need more data")]
+    NeedMoreData,
+}
+
+pub type Av1Result<T> = Result<T, Av1Error>;
+
+/// A shown frame, cropped to its upscaled width and frame height
+#[derive(Debug, Clone)]
+pub struct Picture {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    /// Chroma subsampling, horizontal and vertical
+    pub ss_x: bool,
+    pub ss_y: bool,
+    /// Y, U and V samples row by row; monochrome streams only have Y
+    pub planes: Vec<Vec<u16>>,
+    pub key_frame: bool,
+}
+
+/// A frame whose tile groups are still arriving
+#[derive(Debug)]
+struct PendingFrame {
+    header: Arc<FrameHeader>,
+    /// Tile data by tile number
+    tiles: Vec<Option<Vec<u8>>>,
+}
+
+/// AV1 Decoder
+#[derive(Debug)]
+pub struct Av1Decoder {
+    seq_header: Option<Av1SequenceHeader>,
+    ref_frames: [Option<Arc<RefFrame>>; NUM_REF_FRAMES],
+    pending: Option<PendingFrame>,
+    /// Maximum number of tiles decoded at once
+    threads: usize,
+}
+
+impl Av1Decoder {
+    pub fn new() -> Self {
+        Self::with_threads(std::thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    /// Decoder using at most `threads` threads for the tiles of a frame
+    pub fn with_threads(threads: usize) -> Self {
+        Self {
+            seq_header: None,
+            ref_frames: Default::default(),
+            pending: None,
+            threads: threads.max(1),
+        }
+    }
+
+    /// Sequence header of the stream, once one has been seen
+    pub fn sequence_header(&self) -> Option<&Av1SequenceHeader> {
+        self.seq_header.as_ref()
+    }
+
+    /// Decode a temporal unit; returns the frames it shows
+    pub fn decode(&mut self, data: &[u8]) -> Av1Result<Vec<Picture>> {
+        let mut output = Vec::new();
+        for obu in parse_obus(data)? {
+            let Some(obu_type) = obu.obu_type else { continue };
+            let dropped = obu.has_extension
+                && !matches!(obu_type, ObuType::SequenceHeader | ObuType::TemporalDelimiter)
+                && self.seq_header.as_ref().is_some_and(|s| !s.in_operating_point(obu.temporal_id, obu.spatial_id));
+            if dropped {
+                continue;
+            }
+            match obu_type {
+                ObuType::SequenceHeader => self.sequence_header_obu(obu.payload)?,
+                ObuType::TemporalDelimiter => self.pending = None,
+                ObuType::FrameHeader | ObuType::RedundantFrameHeader if self.pending.is_some() => {}
+                ObuType::FrameHeader | ObuType::RedundantFrameHeader | ObuType::Frame => {
+                    let header = self.frame_header_obu(obu.payload, obu.temporal_id, obu.spatial_id, &mut output)?;
+                    if let (ObuType::Frame, Some(header)) = (obu_type, header) {
+                        let tiles = obu.payload.get(header.header_bytes..).ok_or(Av1Error::NeedMoreData)?;
+                        self.tile_group_obu(tiles, &mut output)?;
+                    }
+                }
+                ObuType::TileGroup => self.tile_group_obu(obu.payload, &mut output)?,
+                _ => {}
+            }
+        }
+        Ok(output)
+    }
+
+    /// Drop a frame whose tile groups are still arriving
+    pub fn flush(&mut self) {
+        self.pending = None;
+    }
+
+    /// Forget the sequence and every reference frame
+    pub fn reset(&mut self) {
+        self.ref_frames = Default::default();
+        self.seq_header = None;
+        self.pending = None;
+    }
+
+    /// Read the configuration OBUs of an `av1C` record
+    pub fn configure(&mut self, extradata: &[u8]) -> Av1Result<()> {
+        if extradata.len() <= 4 || extradata[0] & 0x80 == 0 {
+            return Ok(());
+        }
+        for obu in parse_obus(&extradata[4..])? {
+            if obu.obu_type == Some(ObuType::SequenceHeader) {
+                self.sequence_header_obu(obu.payload)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn sequence_header_obu(&mut self, payload: &[u8]) -> Av1Result<()> {
+        let seq = Av1SequenceHeader::parse(payload)?;
+        if self.seq_header.as_ref().is_some_and(|old| {
+            old.max_frame_width != seq.max_frame_width
+                || old.max_frame_height != seq.max_frame_height
+                || old.bit_depth != seq.bit_depth
+                || old.subsampling_x != seq.subsampling_x
+                || old.subsampling_y != seq.subsampling_y
+        }) {
+            // A new coded video sequence starts with a key frame
+            self.ref_frames = Default::default();
+            self.pending = None;
+        }
+        self.seq_header = Some(seq);
+        Ok(())
+    }
+
+    /// Parse a frame header; returns the header when its tiles follow
+    fn frame_header_obu(
+        &mut self,
+        payload: &[u8],
+        temporal_id: u8,
+        spatial_id: u8,
+        output: &mut Vec<Picture>,
+    ) -> Av1Result<Option<Arc<FrameHeader>>> {
+        let seq = self.seq_header.as_ref().ok_or_else(|| invalid("Frame header before the sequence header"))?;
+        let ref_headers: RefHeaders = std::array::from_fn(|i| self.ref_frames[i].as_ref().map(|r| r.header.clone()));
+        let hdr = FrameHeader::parse(payload, seq, &ref_headers, temporal_id, spatial_id)?;
+        if hdr.show_existing_frame {
+            let frame = self.ref_frames[hdr.frame_to_show_map_idx]
+                .clone()
+                .ok_or_else(|| invalid("Showing an empty reference slot"))?;
+            output.push(picture(&frame));
+            if hdr.frame_type == KEY_FRAME {
+                self.refresh_refs(&frame, hdr.refresh_frame_flags);
+            }
+            return Ok(None);
+        }
+        let hdr = Arc::new(hdr);
+        let num_tiles = hdr.tile_info.cols * hdr.tile_info.rows;
+        self.pending = Some(PendingFrame { header: hdr.clone(), tiles: vec![None; num_tiles] });
+        Ok(Some(hdr))
+    }
+
+    /// Collect the tiles of a tile group and decode the frame once its last
+    /// tile has arrived
+    fn tile_group_obu(&mut self, payload: &[u8], output: &mut Vec<Picture>) -> Av1Result<()> {
+        let pending = self.pending.as_mut().ok_or_else(|| invalid("Tile group without a frame header"))?;
+        let ti = &pending.header.tile_info;
+        let num_tiles = ti.cols * ti.rows;
+        let mut r = BitReader::new(payload);
+        let (mut tg_start, mut tg_end) = (0, num_tiles - 1);
+        if num_tiles > 1 && r.flag()? {
+            let bits = ti.cols_log2 + ti.rows_log2;
+            tg_start = r.f(bits)? as usize;
+            tg_end = r.f(bits)? as usize;
+        }
+        if tg_start > tg_end || tg_end >= num_tiles {
+            return Err(invalid("Tile group outside the frame's tiles"));
+        }
+        let mut pos = r.position().div_ceil(8);
+        for tile_num in tg_start..=tg_end {
+            let size = if tile_num == tg_end {
+                payload.len().checked_sub(pos).ok_or(Av1Error::NeedMoreData)?
+            } else {
+                let n = ti.tile_size_bytes;
+                let bytes = payload.get(pos..pos + n).ok_or(Av1Error::NeedMoreData)?;
+                pos += n;
+                bytes.iter().rev().fold(0usize, |acc, &b| (acc << 8) | b as usize) + 1
+            };
+            let data = payload.get(pos..pos + size).ok_or(Av1Error::NeedMoreData)?;
+            pending.tiles[tile_num] = Some(data.to_vec());
+            pos += size;
+        }
+        if tg_end != num_tiles - 1 {
+            return Ok(());
+        }
+        let Some(PendingFrame { header, tiles }) = self.pending.take() else { return Ok(()) };
+        let tiles = tiles.into_iter().collect::<Option<Vec<_>>>().ok_or_else(|| invalid("Missing tile data"))?;
+        let seq = self.seq_header.as_ref().ok_or_else(|| invalid("Tile group before the sequence header"))?;
+        let frame = Arc::new(decode_frame(seq, &header, &self.ref_frames, &tiles, self.threads)?);
+        if header.show_frame {
+            output.push(picture(&frame));
+        }
+        self.refresh_refs(&frame, header.refresh_frame_flags);
+        Ok(())
+    }
+
+    fn refresh_refs(&mut self, frame: &Arc<RefFrame>, refresh_frame_flags: u8) {
+        for (i, slot) in self.ref_frames.iter_mut().enumerate() {
+            if (refresh_frame_flags >> i) & 1 != 0 {
+                *slot = Some(frame.clone());
+            }
+        }
+    }
+}
+
+/// Crop a decoded frame into an output picture
+fn picture(frame: &RefFrame) -> Picture {
+    let hdr = &frame.header;
+    let (w, h) = (hdr.upscaled_width as usize, hdr.frame_height as usize);
+    let (cw, ch) = ((w + frame.ss_x as usize) >> frame.ss_x as usize, (h + frame.ss_y as usize) >> frame.ss_y as usize);
+    let planes = frame
+        .planes
+        .iter()
+        .enumerate()
+        .map(|(plane, buf)| {
+            let (pw, ph) = if plane == 0 { (w, h) } else { (cw, ch) };
+            (0..ph).flat_map(|y| (0..pw).map(move |x| *buf.get(y, x))).collect()
+        })
+        .collect();
+    Picture {
+        width: w as u32,
+        height: h as u32,
+        bit_depth: frame.bit_depth,
+        ss_x: frame.ss_x,
+        ss_y: frame.ss_y,
+        planes,
+        key_frame: hdr.frame_type == KEY_FRAME,
+    }
+}
+
+fn invalid(msg: &str) -> Av1Error {
+    Av1Error::InvalidBitstream(msg.into())
+}
+
+/// Decode the tiles of a frame and run the in-loop filters
+fn decode_frame(
+    seq: &Av1SequenceHeader,
+    hdr: &Arc<FrameHeader>,
+    slots: &[Option<Arc<RefFrame>>; NUM_REF_FRAMES],
+    tiles: &[Vec<u8>],
+    threads: usize,
+) -> Av1Result<RefFrame> {
+    let (ss_x, ss_y) = (seq.subsampling_x, seq.subsampling_y);
+    let refs: [Option<Arc<RefFrame>>; NUM_REF_FRAMES] = std::array::from_fn(|i| {
+        if i == INTRA_FRAME || hdr.frame_is_intra {
+            None
+        } else {
+            slots[hdr.ref_frame_idx[i - LAST_FRAME]].clone()
+        }
+    });
+    if !hdr.frame_is_intra {
+        for r in refs[LAST_FRAME..].iter() {
+            let r = r.as_ref().ok_or_else(|| invalid("Inter frame references an empty slot"))?;
+            if r.bit_depth != seq.bit_depth || r.ss_x != ss_x || r.ss_y != ss_y {
+                return Err(invalid("Reference frame format differs from the sequence"));
+            }
+        }
+    }
+    let primary = (hdr.primary_ref_frame != PRIMARY_REF_NONE)
+        .then(|| refs[LAST_FRAME + hdr.primary_ref_frame].clone())
+        .flatten();
+    let cdfs = match &primary {
+        Some(p) => (*p.cdfs).clone(),
+        None => CdfContext { coeff: CoeffCdfs::for_q_index(hdr.base_q_idx as u8), ..Default::default() },
+    };
+    let prev_segment_ids = primary
+        .as_ref()
+        .filter(|p| p.mi_rows == hdr.mi_rows && p.mi_cols == hdr.mi_cols)
+        .map(|p| &p.segment_ids);
+    let motion_field = hdr.use_ref_frame_mvs.then(|| MotionField::estimate(seq, hdr, &refs));
+    let num_planes = seq.num_planes();
+    let sb4 = block_width4(if seq.use_128x128_superblock { tables::BLOCK_128X128 } else { tables::BLOCK_64X64 });
+    let (rows4, cols4) = (hdr.mi_rows.next_multiple_of(sb4), hdr.mi_cols.next_multiple_of(sb4));
+    let sub = |plane: usize| if plane == 0 { (0, 0) } else { (ss_x as usize, ss_y as usize) };
+    let f = FrameContext {
+        seq,
+        hdr,
+        refs,
+        motion_field: motion_field.as_ref(),
+        prev_segment_ids,
+        cdfs: &cdfs,
+        bit_depth: seq.bit_depth,
+        ss_x,
+        ss_y,
+        num_planes,
+        plane_widths: std::array::from_fn(|p| (cols4 * 4) >> sub(p).0),
+        plane_heights: std::array::from_fn(|p| (rows4 * 4) >> sub(p).1),
+    };
+
+    let outputs = decode_tiles(&f, tiles, threads)?;
+
+    // Assemble the frame from the tiles
+    let mut planes: Vec<PlaneBuf> =
+        (0..num_planes).map(|p| Grid::new(0, 0, f.plane_heights[p], f.plane_widths[p], 0)).collect();
+    let mut mi = Grid::new(0, 0, rows4, cols4, MiInfo::default());
+    let mut lf_tx: Vec<Grid<u8>> =
+        (0..num_planes).map(|p| Grid::new(0, 0, rows4 >> sub(p).1, cols4 >> sub(p).0, 0)).collect();
+    let mut cdef_idx = Grid::new(0, 0, rows4.div_ceil(16), cols4.div_ceil(16), -1i8);
+    let mut lr_units: Vec<Grid<LrUnit>> = (0..num_planes)
+        .map(|p| {
+            let (sx, sy) = sub(p);
+            let unit_size = hdr.loop_restoration_size[p].max(1);
+            let rows = count_units_in_frame(unit_size, (hdr.frame_height as usize + sy) >> sy);
+            let cols = count_units_in_frame(unit_size, (hdr.upscaled_width as usize + sx) >> sx);
+            Grid::new(0, 0, rows, cols, LrUnit::default())
+        })
+        .collect();
+    for out in &outputs {
+        for (dst, src) in planes.iter_mut().zip(&out.planes) {
+            dst.paste(src);
+        }
+        mi.paste(&out.mi);
+        for (dst, src) in lf_tx.iter_mut().zip(&out.lf_tx) {
+            dst.paste(src);
+        }
+        cdef_idx.paste(&out.cdef_idx);
+        for &(plane, row, col, unit) in &out.lr_units {
+            if lr_units[plane].contains(row, col) {
+                *lr_units[plane].get_mut(row, col) = unit;
+            }
+        }
+    }
+
+    // In-loop filters
+    loopfilter::loop_filter_frame(&f, &mut planes, &mi, &lf_tx);
+    let deblocked = hdr.uses_lr.then(|| planes.clone());
+    cdef::cdef_frame(&f, &mut planes, &mi, &cdef_idx);
+    let (planes, deblocked) = if hdr.use_superres {
+        let upscaled = restoration::superres_upscale(&f, &planes);
+        (upscaled, deblocked.map(|d| restoration::superres_upscale(&f, &d)))
+    } else {
+        (planes, deblocked)
+    };
+    let planes = match deblocked {
+        Some(deblocked) => restoration::loop_restoration(&f, &planes, &deblocked, &lr_units),
+        None => planes,
+    };
+
+    let cdfs = if hdr.disable_frame_end_update_cdf {
+        cdfs.clone()
+    } else {
+        let mut saved = outputs[hdr.tile_info.context_update_tile_id].cdfs.clone();
+        saved.clear_counts();
+        saved
+    };
+    let (mvs, segment_ids) = motion_field_store(seq, hdr, &mi);
+    Ok(RefFrame {
+        header: hdr.clone(),
+        planes,
+        bit_depth: seq.bit_depth,
+        ss_x,
+        ss_y,
+        mi_rows: hdr.mi_rows,
+        mi_cols: hdr.mi_cols,
+        mvs,
+        segment_ids,
+        cdfs: Arc::new(cdfs),
+    })
+}
+
+/// Decode the tiles of a frame, spreading them over at most `threads`
+/// threads. Returns the tile outputs in tile order, or the error of the
+/// first tile whose data does not conform.
+fn decode_tiles(f: &FrameContext, tiles: &[Vec<u8>], threads: usize) -> Av1Result<Vec<TileOutput>> {
+    let cols = f.hdr.tile_info.cols;
+    let workers = threads.clamp(1, tiles.len().max(1));
+    let mut decoded = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..workers)
+            .map(|w| {
+                s.spawn(move || {
+                    (w..tiles.len())
+                        .step_by(workers)
+                        .map(|n| (n, TileDecoder::new(f, &tiles[n], n / cols, n % cols).decode()))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+            .collect::<Vec<_>>()
+    });
+    decoded.sort_by_key(|&(n, _)| n);
+    decoded.into_iter().map(|(_, out)| out).collect()
+}
+
+/// Motion vectors kept for projection by later frames, and the segment map
+fn motion_field_store(
+    seq: &Av1SequenceHeader,
+    hdr: &FrameHeader,
+    mi: &Grid<MiInfo>,
+) -> (Grid<(i8, Mv)>, Grid<u8>) {
+    let mut mvs = Grid::new(0, 0, hdr.mi_rows, hdr.mi_cols, (-1i8, [0, 0]));
+    let mut segment_ids = Grid::new(0, 0, hdr.mi_rows, hdr.mi_cols, 0u8);
+    for row in 0..hdr.mi_rows {
+        for col in 0..hdr.mi_cols {
+            let info = mi.get(row, col);
+            *segment_ids.get_mut(row, col) = info.segment_id;
+            for list in 0..2 {
+                let r = info.ref_frame[list];
+                if r <= INTRA_FRAME as i8 {
+                    continue;
+                }
+                let dist = FrameHeader::relative_dist(seq, hdr.order_hints[r as usize], hdr.order_hint);
+                let mv = info.mv[list];
+                if dist < 0 && mv[0].abs() <= REFMVS_LIMIT && mv[1].abs() <= REFMVS_LIMIT {
+                    *mvs.get_mut(row, col) = (r, mv);
+                }
+            }
+        }
+    }
+    (mvs, segment_ids)
+}
+
+impl Default for Av1Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::TEST_STREAM;
+
+    #[test]
+    fn test_decode_key_and_inter_frame() {
+        let mut d = Av1Decoder::with_threads(2);
+        // Plane sums of the encoder's reconstruction
+        let expected = [[19402, 7484, 7740], [17556, 7262, 7518]];
+        for (data, sums) in TEST_STREAM.iter().zip(expected) {
+            let pictures = d.decode(data).unwrap();
+            assert_eq!(pictures.len(), 1);
+            let picture = &pictures[0];
+            assert_eq!((picture.width, picture.height, picture.bit_depth), (16, 16, 8));
+            assert!(picture.ss_x && picture.ss_y);
+            let got: Vec<u32> = picture.planes.iter().map(|p| p.iter().map(|&v| v as u32).sum()).collect();
+            assert_eq!(got, sums);
+        }
+        assert_eq!(d.sequence_header().map(|s| s.bit_depth), Some(8));
+    }
+
+    #[test]
+    fn test_truncated_tile_is_an_error() {
+        // The key frame OBU with its tile data cut short
+        let mut data = TEST_STREAM[0][..16 + 26].to_vec();
+        data[15] = 26;
+        let mut d = Av1Decoder::with_threads(2);
+        assert!(matches!(d.decode(&data), Err(Av1Error::InvalidBitstream(_))));
+    }
+}
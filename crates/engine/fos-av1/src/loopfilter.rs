@@ -0,0 +1,247 @@
+//! AV1 Deblocking Filter
+//!
+//! Smooths transform and prediction block edges of a reconstructed frame.
+//! All vertical edges of a plane are filtered before its horizontal ones;
+//! within one direction the filters never overlap, so the order of the
+//! edges does not matter.
+
+use super::headers::SEG_LVL_ALT_LF_Y_V;
+use super::state::{Grid, MiInfo, PlaneBuf};
+use super::tables::*;
+use super::tile::FrameContext;
+
+const MAX_LOOP_FILTER: i32 = 63;
+
+/// Deblock every plane of the frame in place
+pub(super) fn loop_filter_frame(f: &FrameContext, planes: &mut [PlaneBuf], mi: &Grid<MiInfo>, lf_tx: &[Grid<u8>]) {
+    let hdr = f.hdr;
+    if hdr.loop_filter_level[0] == 0 && hdr.loop_filter_level[1] == 0 {
+        return;
+    }
+    for (plane, buf) in planes.iter_mut().enumerate().take(f.num_planes) {
+        if plane > 0 && hdr.loop_filter_level[plane + 1] == 0 {
+            continue;
+        }
+        let (sx, sy) = f.sub(plane);
+        for pass in 0..2 {
+            for row in (0..hdr.mi_rows).step_by(1 << sy) {
+                for col in (0..hdr.mi_cols).step_by(1 << sx) {
+                    edge_loop_filter(f, buf, mi, &lf_tx[plane], plane, pass, row, col);
+                }
+            }
+        }
+    }
+}
+
+/// Filter level of a block for one plane and direction
+fn filter_level(f: &FrameContext, info: &MiInfo, plane: usize, pass: usize) -> i32 {
+    let hdr = f.hdr;
+    let i = if plane == 0 { pass } else { plane + 1 };
+    let delta_lf = if hdr.delta_lf_multi { info.delta_lf[i] } else { info.delta_lf[0] } as i32;
+    let mut lvl = (delta_lf + hdr.loop_filter_level[i] as i32).clamp(0, MAX_LOOP_FILTER);
+    let feature = SEG_LVL_ALT_LF_Y_V + i;
+    if f.seg_feature_active(info.segment_id, feature) {
+        lvl = (lvl + hdr.feature_data[info.segment_id as usize][feature]).clamp(0, MAX_LOOP_FILTER);
+    }
+    if hdr.loop_filter_delta_enabled {
+        let shift = lvl >> 5;
+        let rf = info.ref_frame[0].max(0) as usize;
+        lvl += hdr.loop_filter_ref_deltas[rf] << shift;
+        if rf > 0 {
+            let mode_type = info.y_mode >= NEARESTMV && info.y_mode != GLOBALMV && info.y_mode != GLOBAL_GLOBALMV;
+            lvl += hdr.loop_filter_mode_deltas[mode_type as usize] << shift;
+        }
+        lvl = lvl.clamp(0, MAX_LOOP_FILTER);
+    }
+    lvl
+}
+
+/// Filter limits `(limit, blimit, thresh)` for a filter level
+fn filter_limits(sharpness: i32, lvl: i32) -> (i32, i32, i32) {
+    let shift = if sharpness > 4 {
+        2
+    } else if sharpness > 0 {
+        1
+    } else {
+        0
+    };
+    let limit = if sharpness > 0 { (lvl >> shift).clamp(1, 9 - sharpness) } else { (lvl >> shift).max(1) };
+    (limit, 2 * (lvl + 2) + limit, lvl >> 4)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn edge_loop_filter(
+    f: &FrameContext,
+    buf: &mut PlaneBuf,
+    mi: &Grid<MiInfo>,
+    lf_tx: &Grid<u8>,
+    plane: usize,
+    pass: usize,
+    row: usize,
+    col: usize,
+) {
+    let hdr = f.hdr;
+    let (sx, sy) = f.sub(plane);
+    let (x, y) = (col * 4, row * 4);
+    if x >= hdr.frame_width as usize || y >= hdr.frame_height as usize || (pass == 0 && x == 0) || (pass == 1 && y == 0)
+    {
+        return;
+    }
+    let (xp, yp) = (x >> sx, y >> sy);
+    let (row, col) = (row | sy, col | sx);
+    let (prev_row, prev_col) = if pass == 0 { (row, col - (1 << sx)) } else { (row - (1 << sy), col) };
+    let info = mi.get(row, col);
+    let tx = *lf_tx.get(row >> sy, col >> sx) as usize;
+    let prev_tx = *lf_tx.get(prev_row >> sy, prev_col >> sx) as usize;
+    let plane_size = subsampled_size(info.mi_size as usize, sx == 1, sy == 1);
+    let (is_block_edge, is_tx_edge, base_size) = if pass == 0 {
+        (xp % block_width(plane_size) == 0, xp % tx_width(tx) == 0, tx_width(prev_tx).min(tx_width(tx)))
+    } else {
+        (yp % block_height(plane_size) == 0, yp % tx_height(tx) == 0, tx_height(prev_tx).min(tx_height(tx)))
+    };
+    let skip = info.skip && info.is_inter;
+    if !is_tx_edge || !(is_block_edge || !skip || !info.is_inter) {
+        return;
+    }
+    let filter_size = if plane == 0 { base_size.min(16) } else { base_size.min(8) };
+    let mut lvl = filter_level(f, info, plane, pass);
+    if lvl == 0 {
+        lvl = filter_level(f, mi.get(prev_row, prev_col), plane, pass);
+    }
+    if lvl == 0 {
+        return;
+    }
+    let (limit, blimit, thresh) = filter_limits(hdr.loop_filter_sharpness as i32, lvl);
+    let bd = f.bit_depth as u32;
+    let stride = buf.cols;
+    // Distance between the taps across the edge and between filtered lines
+    let (step, line) = if pass == 0 { (1, stride) } else { (stride, 1) };
+    let start = (yp - buf.row0) * stride + xp - buf.col0;
+    for i in 0..4 {
+        sample_filter(&mut buf.data, start + i * line, step, filter_size, plane, limit, blimit, thresh, bd);
+    }
+}
+
+/// Filter the samples across the edge in front of `pos`
+#[allow(clippy::too_many_arguments)]
+fn sample_filter(
+    data: &mut [u16],
+    pos: usize,
+    step: usize,
+    filter_size: usize,
+    plane: usize,
+    limit: i32,
+    blimit: i32,
+    thresh: i32,
+    bd: u32,
+) {
+    let reach = if filter_size >= 16 { 7 } else { 4 };
+    // s[reach + k] is q_k for k >= 0 and p_(-k-1) below that
+    let mut s = [0i32; 14];
+    for (k, v) in s.iter_mut().enumerate().take(2 * reach) {
+        *v = data[pos + k * step - reach * step] as i32;
+    }
+    let p = |i: usize| s[reach - 1 - i];
+    let q = |i: usize| s[reach + i];
+    let shift = bd - 8;
+    let hev = (p(1) - p(0)).abs() > thresh << shift || (q(1) - q(0)).abs() > thresh << shift;
+    let filter_len = match (filter_size, plane) {
+        (4, _) => 4,
+        (_, p) if p > 0 => 6,
+        (8, _) => 8,
+        _ => 16,
+    };
+    let (limit, blimit) = (limit << shift, blimit << shift);
+    let mut mask = (p(1) - p(0)).abs() > limit
+        || (q(1) - q(0)).abs() > limit
+        || (p(0) - q(0)).abs() * 2 + (p(1) - q(1)).abs() / 2 > blimit;
+    if filter_len >= 6 {
+        mask |= (p(2) - p(1)).abs() > limit || (q(2) - q(1)).abs() > limit;
+    }
+    if filter_len >= 8 {
+        mask |= (p(3) - p(2)).abs() > limit || (q(3) - q(2)).abs() > limit;
+    }
+    if mask {
+        return;
+    }
+    let flat_thresh = 1 << shift;
+    let flat = filter_size >= 8 && {
+        let n = if filter_len >= 8 { 4 } else { 3 };
+        (1..n).all(|i| (p(i) - p(0)).abs() <= flat_thresh && (q(i) - q(0)).abs() <= flat_thresh)
+    };
+    let flat2 = filter_size >= 16
+        && (4..7).all(|i| (p(i) - p(0)).abs() <= flat_thresh && (q(i) - q(0)).abs() <= flat_thresh);
+    if filter_size == 4 || !flat {
+        narrow_filter(data, pos, step, hev, bd, &s[reach - 2..reach + 2]);
+    } else if filter_size == 8 || !flat2 {
+        wide_filter(data, pos, step, plane, 3, &s, reach);
+    } else {
+        wide_filter(data, pos, step, plane, 4, &s, reach);
+    }
+}
+
+/// Four tap filter of `p1 p0 | q0 q1`
+fn narrow_filter(data: &mut [u16], pos: usize, step: usize, hev: bool, bd: u32, s: &[i32]) {
+    let clamp4 = |x: i32| x.clamp(-(1 << (bd - 1)), (1 << (bd - 1)) - 1);
+    let offset = 0x80 << (bd - 8);
+    let (ps1, ps0, qs0, qs1) = (s[0] - offset, s[1] - offset, s[2] - offset, s[3] - offset);
+    let filter = if hev { clamp4(ps1 - qs1) } else { 0 };
+    let filter = clamp4(filter + 3 * (qs0 - ps0));
+    let filter1 = clamp4(filter + 4) >> 3;
+    let filter2 = clamp4(filter + 3) >> 3;
+    data[pos] = (clamp4(qs0 - filter1) + offset) as u16;
+    data[pos - step] = (clamp4(ps0 + filter2) + offset) as u16;
+    if !hev {
+        let filter = (filter1 + 1) >> 1;
+        data[pos + step] = (clamp4(qs1 - filter) + offset) as u16;
+        data[pos - 2 * step] = (clamp4(ps1 + filter) + offset) as u16;
+    }
+}
+
+/// Smoothing filter over `2 * n` samples around the edge
+fn wide_filter(data: &mut [u16], pos: usize, step: usize, plane: usize, log2_size: u32, s: &[i32], reach: usize) {
+    let n: i32 = if log2_size == 4 {
+        6
+    } else if plane == 0 {
+        3
+    } else {
+        2
+    };
+    let n2 = if log2_size == 3 && plane == 0 { 0 } else { 1 };
+    let at = |k: i32| s[(reach as i32 + k) as usize];
+    let mut out = [0i32; 12];
+    for i in -n..n {
+        let mut t = 0;
+        for j in -n..=n {
+            let k = (i + j).clamp(-(n + 1), n);
+            let tap = if j.abs() <= n2 { 2 } else { 1 };
+            t += at(k) * tap;
+        }
+        out[(i + n) as usize] = (t + (1 << (log2_size - 1))) >> log2_size;
+    }
+    for i in -n..n {
+        let p = (pos as isize + i as isize * step as isize) as usize;
+        data[p] = out[(i + n) as usize] as u16;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_limits() {
+        assert_eq!(filter_limits(0, 0), (1, 5, 0));
+        assert_eq!(filter_limits(0, 32), (32, 100, 2));
+        assert_eq!(filter_limits(5, 63), (4, 134, 3));
+    }
+
+    #[test]
+    fn test_wide_filter_keeps_flat_step() {
+        // A flat area stays flat
+        let mut data = vec![100u16; 16];
+        let s: Vec<i32> = data[..14].iter().map(|&v| v as i32).collect();
+        wide_filter(&mut data, 7, 1, 0, 4, &s, 7);
+        assert!(data.iter().all(|&v| v == 100));
+    }
+}
@@ -0,0 +1,765 @@
+//! AV1 Motion Vector Prediction
+//!
+//! Candidate list construction from spatial neighbours and the projected
+//! motion field, motion vector syntax and warp sample collection.
+
+use super::headers::*;
+use super::state::{Grid, Mv, RefFrame};
+use super::tables::*;
+use super::tile::{TileDecoder, MV_LOW, MV_UPP, NONE_FRAME};
+use std::sync::Arc;
+
+const REF_CAT_LEVEL: u32 = 640;
+const MAX_REF_MV_STACK_SIZE: usize = 8;
+const MV_BORDER: i32 = 128;
+const MAX_FRAME_DISTANCE: i32 = 31;
+pub(super) const REFMVS_LIMIT: i32 = (1 << 12) - 1;
+const INVALID_MV: i32 = -(1 << 15);
+const LEAST_SQUARES_SAMPLES_MAX: usize = 8;
+
+const DIV_MULT: [i32; 32] = [
+    0, 16384, 8192, 5461, 4096, 3276, 2730, 2340, 2048, 1820, 1638, 1489, 1365, 1260, 1170, 1092, 1024, 963, 910, 862,
+    819, 780, 744, 712, 682, 655, 630, 606, 585, 564, 546, 528,
+];
+
+fn round2_signed(x: i64, n: u32) -> i64 {
+    if n == 0 {
+        return x;
+    }
+    if x >= 0 { (x + (1 << (n - 1))) >> n } else { -((-x + (1 << (n - 1))) >> n) }
+}
+
+/// Scale a motion vector by the ratio of two frame distances
+fn get_mv_projection(mv: Mv, numerator: i32, denominator: i32) -> Mv {
+    let den = denominator.min(MAX_FRAME_DISTANCE);
+    let num = numerator.clamp(-MAX_FRAME_DISTANCE, MAX_FRAME_DISTANCE);
+    mv.map(|v| {
+        let scaled = round2_signed(v as i64 * num as i64 * DIV_MULT[den as usize] as i64, 14) as i32;
+        scaled.clamp(-(1 << 14) + 1, (1 << 14) - 1)
+    })
+}
+
+fn has_newmv(mode: u8) -> bool {
+    matches!(mode, NEWMV | NEW_NEWMV | NEAR_NEWMV | NEW_NEARMV | NEAREST_NEWMV | NEW_NEARESTMV)
+}
+
+/// Motion vectors of earlier frames projected onto the current frame, per
+/// 8x8 block: the vector and the distance it spans
+pub(super) struct MotionField {
+    mvs: Grid<(Mv, i32)>,
+}
+
+impl MotionField {
+    /// Project the stored vectors of the references (indexed by reference
+    /// frame) that lie on either side of the current frame
+    pub fn estimate(seq: &Av1SequenceHeader, hdr: &FrameHeader, refs: &[Option<Arc<RefFrame>>; NUM_REF_FRAMES]) -> Self {
+        let (h8, w8) = (hdr.mi_rows >> 1, hdr.mi_cols >> 1);
+        let mut field = Self { mvs: Grid::new(0, 0, h8, w8, ([INVALID_MV, 0], 0)) };
+        let dist = |a: u32, b: u32| FrameHeader::relative_dist(seq, a, b);
+        let mut ref_stamp = 2i32;
+        if let Some(last) = &refs[LAST_FRAME] {
+            if last.header.order_hints[ALTREF_FRAME] != hdr.order_hints[GOLDEN_FRAME] {
+                field.project(seq, hdr, refs, LAST_FRAME, true);
+            }
+            ref_stamp -= 1;
+        }
+        if dist(hdr.order_hints[BWDREF_FRAME], hdr.order_hint) > 0 && field.project(seq, hdr, refs, BWDREF_FRAME, false) {
+            ref_stamp -= 1;
+        }
+        if dist(hdr.order_hints[ALTREF2_FRAME], hdr.order_hint) > 0 && field.project(seq, hdr, refs, ALTREF2_FRAME, false)
+        {
+            ref_stamp -= 1;
+        }
+        if dist(hdr.order_hints[ALTREF_FRAME], hdr.order_hint) > 0
+            && ref_stamp >= 0
+            && field.project(seq, hdr, refs, ALTREF_FRAME, false)
+        {
+            ref_stamp -= 1;
+        }
+        if ref_stamp >= 0 {
+            field.project(seq, hdr, refs, LAST2_FRAME, true);
+        }
+        field
+    }
+
+    fn project(
+        &mut self,
+        seq: &Av1SequenceHeader,
+        hdr: &FrameHeader,
+        refs: &[Option<Arc<RefFrame>>; NUM_REF_FRAMES],
+        src: usize,
+        backward: bool,
+    ) -> bool {
+        let Some(start) = &refs[src] else { return false };
+        let sh = &start.header;
+        if sh.frame_is_intra || start.mi_rows != hdr.mi_rows || start.mi_cols != hdr.mi_cols {
+            return false;
+        }
+        let mut start_to_cur = FrameHeader::relative_dist(seq, sh.order_hint, hdr.order_hint);
+        if backward {
+            start_to_cur = -start_to_cur;
+        }
+        let ref_offsets: [i32; NUM_REF_FRAMES] =
+            std::array::from_fn(|r| FrameHeader::relative_dist(seq, sh.order_hint, sh.order_hints[r]));
+        let (h8, w8) = (self.mvs.rows as i32, self.mvs.cols as i32);
+        for y8 in 0..h8 {
+            for x8 in 0..w8 {
+                let &(rf, mv) = start.mvs.get(y8 as usize * 2 + 1, x8 as usize * 2 + 1);
+                if rf <= INTRA_FRAME as i8 {
+                    continue;
+                }
+                let offset = ref_offsets[rf as usize];
+                if offset <= 0 || offset > MAX_FRAME_DISTANCE || start_to_cur.abs() > MAX_FRAME_DISTANCE {
+                    continue;
+                }
+                let proj = get_mv_projection(mv, start_to_cur, offset);
+                let shift = |v: i32| if v >= 0 { v >> 6 } else { -((-v) >> 6) };
+                let (dy, dx) = (shift(proj[0]), shift(proj[1]));
+                let (row, col) = if backward { (y8 - dy, x8 - dx) } else { (y8 + dy, x8 + dx) };
+                let (base_row, base_col) = ((y8 >> 3) << 3, (x8 >> 3) << 3);
+                if row < 0 || row >= h8 || col < 0 || col >= w8 {
+                    continue;
+                }
+                if row < base_row || row >= base_row + 8 || col < base_col - 8 || col >= base_col + 16 {
+                    continue;
+                }
+                *self.mvs.get_mut(row as usize, col as usize) = (mv, offset);
+            }
+        }
+        true
+    }
+}
+
+impl TileDecoder<'_> {
+    /// Build the candidate list of motion vectors for the current block
+    pub(super) fn find_mv_stack(&mut self, is_compound: bool) {
+        let (bw4, bh4) = (self.b.bw4, self.b.bh4);
+        self.b.num_mv_found = 0;
+        self.b.new_mv_count = 0;
+        self.b.ref_stack_mv = [[[0; 2]; 2]; 9];
+        self.b.weight_stack = [0; 9];
+        self.b.global_mvs[0] = self.setup_global_mv(0);
+        if is_compound {
+            self.b.global_mvs[1] = self.setup_global_mv(1);
+        }
+        self.b.found_match = false;
+        self.scan_row(-1, is_compound);
+        let mut found_above = self.b.found_match;
+        self.b.found_match = false;
+        self.scan_col(-1, is_compound);
+        let mut found_left = self.b.found_match;
+        self.b.found_match = false;
+        if bw4.max(bh4) <= 16 {
+            self.scan_point(-1, bw4 as isize, is_compound);
+        }
+        found_above |= self.b.found_match;
+        let close_matches = found_above as usize + found_left as usize;
+        let num_nearest = self.b.num_mv_found;
+        let num_new = self.b.new_mv_count;
+        for w in &mut self.b.weight_stack[..num_nearest] {
+            *w += REF_CAT_LEVEL;
+        }
+        self.b.zero_mv_context = 0;
+        if self.f.hdr.use_ref_frame_mvs {
+            self.temporal_scan(is_compound);
+        }
+        self.b.found_match = false;
+        self.scan_point(-1, -1, is_compound);
+        found_above |= self.b.found_match;
+        self.b.found_match = false;
+        self.scan_row(-3, is_compound);
+        found_above |= self.b.found_match;
+        self.b.found_match = false;
+        self.scan_col(-3, is_compound);
+        found_left |= self.b.found_match;
+        self.b.found_match = false;
+        if bh4 > 1 {
+            self.scan_row(-5, is_compound);
+        }
+        found_above |= self.b.found_match;
+        self.b.found_match = false;
+        if bw4 > 1 {
+            self.scan_col(-5, is_compound);
+        }
+        found_left |= self.b.found_match;
+        let total_matches = found_above as usize + found_left as usize;
+        self.sort_stack(0, num_nearest);
+        self.sort_stack(num_nearest, self.b.num_mv_found);
+        if self.b.num_mv_found < 2 {
+            self.extra_search(is_compound);
+        }
+        self.context_and_clamping(is_compound, num_new, close_matches, total_matches);
+    }
+
+    fn setup_global_mv(&self, list: usize) -> Mv {
+        let hdr = self.f.hdr;
+        let b = &self.b;
+        let rf = b.ref_frame[list];
+        let mut mv = if rf <= INTRA_FRAME as i8 || hdr.gm_type[rf as usize] == IDENTITY {
+            [0, 0]
+        } else {
+            let gm = &hdr.gm_params[rf as usize];
+            if hdr.gm_type[rf as usize] == TRANSLATION {
+                // The reference decoder assigns the translation to swapped
+                // components and the spec follows it
+                [gm[0] >> (WARPEDMODEL_PREC_BITS - 3), gm[1] >> (WARPEDMODEL_PREC_BITS - 3)]
+            } else {
+                let x = (b.mi_col * 4 + block_width(b.mi_size) / 2) as i64 - 1;
+                let y = (b.mi_row * 4 + block_height(b.mi_size) / 2) as i64 - 1;
+                let one = 1i64 << WARPEDMODEL_PREC_BITS;
+                let xc = (gm[2] as i64 - one) * x + gm[3] as i64 * y + gm[0] as i64;
+                let yc = gm[4] as i64 * x + (gm[5] as i64 - one) * y + gm[1] as i64;
+                if hdr.allow_high_precision_mv {
+                    [
+                        round2_signed(yc, WARPEDMODEL_PREC_BITS - 3) as i32,
+                        round2_signed(xc, WARPEDMODEL_PREC_BITS - 3) as i32,
+                    ]
+                } else {
+                    [
+                        round2_signed(yc, WARPEDMODEL_PREC_BITS - 2) as i32 * 2,
+                        round2_signed(xc, WARPEDMODEL_PREC_BITS - 2) as i32 * 2,
+                    ]
+                }
+            }
+        };
+        self.lower_mv_precision(&mut mv);
+        mv
+    }
+
+    fn lower_mv_precision(&self, mv: &mut Mv) {
+        let hdr = self.f.hdr;
+        if hdr.allow_high_precision_mv {
+            return;
+        }
+        for v in mv.iter_mut() {
+            if hdr.force_integer_mv {
+                let a = (v.abs() + 3) >> 3;
+                *v = if *v > 0 { a << 3 } else { -(a << 3) };
+            } else if *v & 1 != 0 {
+                *v += if *v > 0 { -1 } else { 1 };
+            }
+        }
+    }
+
+    fn scan_row(&mut self, delta_row: isize, is_compound: bool) {
+        let b = &self.b;
+        let bw4 = b.bw4;
+        let end4 = bw4.min(self.f.hdr.mi_cols - b.mi_col).min(16);
+        let (mut delta_row, mut delta_col) = (delta_row, 0);
+        let use_step16 = bw4 >= 16;
+        if delta_row.abs() > 1 {
+            delta_row += (b.mi_row & 1) as isize;
+            delta_col = 1 - (b.mi_col & 1) as isize;
+        }
+        let mut i = 0;
+        while i < end4 {
+            let mv_row = self.b.mi_row as isize + delta_row;
+            let mv_col = self.b.mi_col as isize + delta_col + i as isize;
+            if !self.is_inside(mv_row, mv_col) {
+                break;
+            }
+            let mut len = bw4.min(block_width4(self.mi(mv_row as usize, mv_col as usize).mi_size as usize));
+            if delta_row.abs() > 1 {
+                len = len.max(2);
+            }
+            if use_step16 {
+                len = len.max(4);
+            }
+            self.add_ref_mv_candidate(mv_row as usize, mv_col as usize, is_compound, len as u32 * 2);
+            i += len;
+        }
+    }
+
+    fn scan_col(&mut self, delta_col: isize, is_compound: bool) {
+        let b = &self.b;
+        let bh4 = b.bh4;
+        let end4 = bh4.min(self.f.hdr.mi_rows - b.mi_row).min(16);
+        let (mut delta_row, mut delta_col) = (0, delta_col);
+        let use_step16 = bh4 >= 16;
+        if delta_col.abs() > 1 {
+            delta_row = 1 - (b.mi_row & 1) as isize;
+            delta_col += (b.mi_col & 1) as isize;
+        }
+        let mut i = 0;
+        while i < end4 {
+            let mv_row = self.b.mi_row as isize + delta_row + i as isize;
+            let mv_col = self.b.mi_col as isize + delta_col;
+            if !self.is_inside(mv_row, mv_col) {
+                break;
+            }
+            let mut len = bh4.min(block_height4(self.mi(mv_row as usize, mv_col as usize).mi_size as usize));
+            if delta_col.abs() > 1 {
+                len = len.max(2);
+            }
+            if use_step16 {
+                len = len.max(4);
+            }
+            self.add_ref_mv_candidate(mv_row as usize, mv_col as usize, is_compound, len as u32 * 2);
+            i += len;
+        }
+    }
+
+    fn scan_point(&mut self, delta_row: isize, delta_col: isize, is_compound: bool) {
+        let mv_row = self.b.mi_row as isize + delta_row;
+        let mv_col = self.b.mi_col as isize + delta_col;
+        if self.is_inside(mv_row, mv_col) && self.mi_decoded(delta_row, delta_col) {
+            self.add_ref_mv_candidate(mv_row as usize, mv_col as usize, is_compound, 4);
+        }
+    }
+
+    /// Whether a unit at an offset from the current block has been decoded;
+    /// only the top-right neighbour may not have been
+    fn mi_decoded(&self, delta_row: isize, delta_col: isize) -> bool {
+        if delta_row >= 0 || delta_col < self.b.bw4 as isize {
+            return true;
+        }
+        let mask = block_width4(self.f.sb_size()) - 1;
+        let row = (self.b.mi_row & mask) as isize + delta_row;
+        let col = (self.b.mi_col & mask) as isize + delta_col;
+        self.block_decoded(0, row, col)
+    }
+
+    fn is_global_mv_block(&self, mode: u8, mi_size: u8, rf: i8) -> bool {
+        let large = block_width(mi_size as usize).min(block_height(mi_size as usize)) >= 8;
+        (mode == GLOBALMV || mode == GLOBAL_GLOBALMV) && self.f.hdr.gm_type[rf as usize] > TRANSLATION && large
+    }
+
+    fn add_ref_mv_candidate(&mut self, row: usize, col: usize, is_compound: bool, weight: u32) {
+        let cand = *self.mi(row, col);
+        if !cand.is_inter {
+            return;
+        }
+        let rf = self.b.ref_frame;
+        if !is_compound {
+            for list in 0..2 {
+                if cand.ref_frame[list] != rf[0] {
+                    continue;
+                }
+                let mut mv = if self.is_global_mv_block(cand.y_mode, cand.mi_size, rf[0]) {
+                    self.b.global_mvs[0]
+                } else {
+                    cand.mv[list]
+                };
+                self.lower_mv_precision(&mut mv);
+                if has_newmv(cand.y_mode) {
+                    self.b.new_mv_count += 1;
+                }
+                self.b.found_match = true;
+                self.push_candidate([mv, [0, 0]], false, weight);
+            }
+        } else if cand.ref_frame == rf {
+            let mut mvs = cand.mv;
+            for (list, mv) in mvs.iter_mut().enumerate() {
+                if self.is_global_mv_block(cand.y_mode, cand.mi_size, rf[list]) {
+                    *mv = self.b.global_mvs[list];
+                }
+                self.lower_mv_precision(mv);
+            }
+            self.b.found_match = true;
+            self.push_candidate(mvs, true, weight);
+            if has_newmv(cand.y_mode) {
+                self.b.new_mv_count += 1;
+            }
+        }
+    }
+
+    /// Add weight to a matching stack entry, or append a new one
+    fn push_candidate(&mut self, mvs: [Mv; 2], is_compound: bool, weight: u32) {
+        let b = &mut self.b;
+        let n = b.num_mv_found;
+        let found = b.ref_stack_mv[..n].iter().position(|s| s[0] == mvs[0] && (!is_compound || s[1] == mvs[1]));
+        if let Some(idx) = found {
+            b.weight_stack[idx] += weight;
+        } else if n < MAX_REF_MV_STACK_SIZE {
+            b.ref_stack_mv[n][0] = mvs[0];
+            if is_compound {
+                b.ref_stack_mv[n][1] = mvs[1];
+            }
+            b.weight_stack[n] = weight;
+            b.num_mv_found += 1;
+        }
+    }
+
+    fn temporal_scan(&mut self, is_compound: bool) {
+        let (bw4, bh4) = (self.b.bw4 as isize, self.b.bh4 as isize);
+        let step_w4 = if bw4 >= 16 { 4 } else { 2 };
+        let step_h4 = if bh4 >= 16 { 4 } else { 2 };
+        for delta_row in (0..bh4.min(16)).step_by(step_h4) {
+            for delta_col in (0..bw4.min(16)).step_by(step_w4) {
+                self.add_tpl_ref_mv(delta_row, delta_col, is_compound);
+            }
+        }
+        let allow_extension = (2..16).contains(&bh4) && (2..16).contains(&bw4);
+        if allow_extension {
+            for (delta_row, delta_col) in [(bh4, -2), (bh4, bw4), (bh4 - 2, bw4)] {
+                let row = (self.b.mi_row & 15) as isize + delta_row;
+                let col = (self.b.mi_col & 15) as isize + delta_col;
+                if (0..16).contains(&row) && (0..16).contains(&col) {
+                    self.add_tpl_ref_mv(delta_row, delta_col, is_compound);
+                }
+            }
+        }
+    }
+
+    fn add_tpl_ref_mv(&mut self, delta_row: isize, delta_col: isize, is_compound: bool) {
+        let mv_row = (self.b.mi_row as isize + delta_row) | 1;
+        let mv_col = (self.b.mi_col as isize + delta_col) | 1;
+        if !self.is_inside(mv_row, mv_col) {
+            return;
+        }
+        let Some(field) = self.f.motion_field else { return };
+        let at_origin = delta_row == 0 && delta_col == 0;
+        if at_origin {
+            self.b.zero_mv_context = 1;
+        }
+        let &(mv, offset) = field.mvs.get(mv_row as usize >> 1, mv_col as usize >> 1);
+        if mv[0] == INVALID_MV {
+            return;
+        }
+        let hdr = self.f.hdr;
+        let seq = self.f.seq;
+        let mut mvs = [[0; 2]; 2];
+        for (list, proj) in mvs.iter_mut().enumerate().take(1 + is_compound as usize) {
+            let rf = self.b.ref_frame[list] as usize;
+            let cur_offset = FrameHeader::relative_dist(seq, hdr.order_hint, hdr.order_hints[rf]);
+            *proj = get_mv_projection(mv, cur_offset, offset);
+            self.lower_mv_precision(proj);
+        }
+        if at_origin {
+            let g = self.b.global_mvs;
+            let far = |a: Mv, b: Mv| (a[0] - b[0]).abs() >= 16 || (a[1] - b[1]).abs() >= 16;
+            self.b.zero_mv_context = (far(mvs[0], g[0]) || (is_compound && far(mvs[1], g[1]))) as usize;
+        }
+        self.push_candidate(mvs, is_compound, 2);
+    }
+
+    fn sort_stack(&mut self, start: usize, mut end: usize) {
+        let b = &mut self.b;
+        while end > start {
+            let mut new_end = start;
+            for idx in start + 1..end {
+                if b.weight_stack[idx - 1] < b.weight_stack[idx] {
+                    b.weight_stack.swap(idx - 1, idx);
+                    b.ref_stack_mv.swap(idx - 1, idx);
+                    new_end = idx;
+                }
+            }
+            end = new_end;
+        }
+    }
+
+    /// Fill a short list from neighbours using other references
+    fn extra_search(&mut self, is_compound: bool) {
+        let mut ref_id: [Vec<Mv>; 2] = Default::default();
+        let mut ref_diff: [Vec<Mv>; 2] = Default::default();
+        let hdr = self.f.hdr;
+        let b = &self.b;
+        let w4 = b.bw4.min(16).min(hdr.mi_cols - b.mi_col);
+        let h4 = b.bh4.min(16).min(hdr.mi_rows - b.mi_row);
+        let num4x4 = w4.min(h4);
+        for pass in 0..2 {
+            let mut idx = 0;
+            while idx < num4x4 && self.b.num_mv_found < 2 {
+                let (mv_row, mv_col) = if pass == 0 {
+                    (self.b.mi_row as isize - 1, (self.b.mi_col + idx) as isize)
+                } else {
+                    ((self.b.mi_row + idx) as isize, self.b.mi_col as isize - 1)
+                };
+                if !self.is_inside(mv_row, mv_col) {
+                    break;
+                }
+                let cand = *self.mi(mv_row as usize, mv_col as usize);
+                self.add_extra_mv_candidate(&cand, is_compound, &mut ref_id, &mut ref_diff);
+                idx += if pass == 0 { block_width4(cand.mi_size as usize) } else { block_height4(cand.mi_size as usize) };
+            }
+            if self.b.num_mv_found >= 2 {
+                break;
+            }
+        }
+        let b = &mut self.b;
+        if is_compound {
+            let mut combined = [[[0; 2]; 2]; 2];
+            for list in 0..2 {
+                let candidates = ref_id[list].iter().chain(ref_diff[list].iter()).chain(std::iter::repeat(&b.global_mvs[list]));
+                for (slot, mv) in combined.iter_mut().zip(candidates) {
+                    slot[list] = *mv;
+                }
+            }
+            if b.num_mv_found == 1 {
+                let n = b.num_mv_found;
+                b.ref_stack_mv[n] = if combined[0] == b.ref_stack_mv[0] { combined[1] } else { combined[0] };
+                b.weight_stack[n] = 2;
+                b.num_mv_found += 1;
+            } else {
+                for mvs in combined {
+                    let n = b.num_mv_found;
+                    b.ref_stack_mv[n] = mvs;
+                    b.weight_stack[n] = 2;
+                    b.num_mv_found += 1;
+                }
+            }
+        } else {
+            for idx in b.num_mv_found..2 {
+                b.ref_stack_mv[idx][0] = b.global_mvs[0];
+            }
+        }
+    }
+
+    fn add_extra_mv_candidate(
+        &mut self,
+        cand: &super::state::MiInfo,
+        is_compound: bool,
+        ref_id: &mut [Vec<Mv>; 2],
+        ref_diff: &mut [Vec<Mv>; 2],
+    ) {
+        let sign_bias = &self.f.hdr.ref_frame_sign_bias;
+        for cand_list in 0..2 {
+            let cand_ref = cand.ref_frame[cand_list];
+            if cand_ref <= INTRA_FRAME as i8 {
+                continue;
+            }
+            let cand_ref = cand_ref as usize;
+            if is_compound {
+                for list in 0..2 {
+                    let mut mv = cand.mv[cand_list];
+                    let rf = self.b.ref_frame[list] as usize;
+                    if cand_ref == rf && ref_id[list].len() < 2 {
+                        ref_id[list].push(mv);
+                    } else if ref_diff[list].len() < 2 {
+                        if sign_bias[cand_ref] != sign_bias[rf] {
+                            mv = mv.map(|v| -v);
+                        }
+                        ref_diff[list].push(mv);
+                    }
+                }
+            } else {
+                let mut mv = cand.mv[cand_list];
+                if sign_bias[cand_ref] != sign_bias[self.b.ref_frame[0] as usize] {
+                    mv = mv.map(|v| -v);
+                }
+                let b = &mut self.b;
+                let n = b.num_mv_found;
+                if !b.ref_stack_mv[..n].iter().any(|s| s[0] == mv) {
+                    b.ref_stack_mv[n][0] = mv;
+                    b.weight_stack[n] = 2;
+                    b.num_mv_found += 1;
+                }
+            }
+        }
+    }
+
+    fn context_and_clamping(&mut self, is_compound: bool, num_new: usize, close_matches: usize, total_matches: usize) {
+        let hdr = self.f.hdr;
+        let b = &mut self.b;
+        let n = b.num_mv_found;
+        for idx in 0..n {
+            b.drl_ctx_stack[idx] = if idx + 1 < n {
+                let (w0, w1) = (b.weight_stack[idx], b.weight_stack[idx + 1]);
+                if w0 >= REF_CAT_LEVEL {
+                    (w1 < REF_CAT_LEVEL) as usize
+                } else {
+                    2
+                }
+            } else {
+                0
+            };
+        }
+        let (mi_row, mi_col) = (b.mi_row as i32, b.mi_col as i32);
+        let (bw4, bh4) = (b.bw4 as i32, b.bh4 as i32);
+        let to_top = -(mi_row * 4 * 8);
+        let to_bottom = (hdr.mi_rows as i32 - bh4 - mi_row) * 4 * 8;
+        let to_left = -(mi_col * 4 * 8);
+        let to_right = (hdr.mi_cols as i32 - bw4 - mi_col) * 4 * 8;
+        let (border_row, border_col) = (MV_BORDER + bh4 * 4 * 8, MV_BORDER + bw4 * 4 * 8);
+        for list in 0..1 + is_compound as usize {
+            for entry in &mut b.ref_stack_mv[..n] {
+                let mv = &mut entry[list];
+                mv[0] = mv[0].clamp(to_top - border_row, to_bottom + border_row);
+                mv[1] = mv[1].clamp(to_left - border_col, to_right + border_col);
+            }
+        }
+        (b.new_mv_context, b.ref_mv_context) = match close_matches {
+            0 => (total_matches.min(1), total_matches),
+            1 => (3 - num_new.min(1), 2 + total_matches),
+            _ => (5 - num_new.min(1), 5),
+        };
+    }
+
+    /// Derive the motion vectors of the block from its mode
+    pub(super) fn assign_mv(&mut self, is_compound: bool) {
+        for list in 0..1 + is_compound as usize {
+            let b = &self.b;
+            let mode = if b.use_intrabc { NEWMV } else { compound_component_mode(b.y_mode, list) };
+            let pred = if b.use_intrabc {
+                let mut pred = b.ref_stack_mv[0][0];
+                if pred == [0, 0] {
+                    pred = b.ref_stack_mv[1][0];
+                }
+                if pred == [0, 0] {
+                    let sb4 = block_height4(self.f.sb_size()) as i32;
+                    pred = if (b.mi_row as i32) - sb4 < self.mi_row_start as i32 {
+                        [0, -(sb4 * 4 + INTRABC_DELAY_PIXELS) * 8]
+                    } else {
+                        [-(sb4 * 4 * 8), 0]
+                    };
+                }
+                pred
+            } else if mode == GLOBALMV {
+                b.global_mvs[list]
+            } else {
+                let pos = if mode == NEARESTMV || (mode == NEWMV && b.num_mv_found <= 1) { 0 } else { b.ref_mv_idx };
+                b.ref_stack_mv[pos][list]
+            };
+            self.b.pred_mv[list] = pred;
+            self.b.mv[list] = if mode == NEWMV { self.read_mv(list) } else { pred };
+        }
+    }
+
+    fn read_mv(&mut self, list: usize) -> Mv {
+        let ctx = self.b.use_intrabc as usize;
+        let joint = self.sd.read_symbol(&mut self.cdf.mv[ctx].joints);
+        let mut diff = [0, 0];
+        if joint == MV_JOINT_HZVNZ || joint == MV_JOINT_HNZVNZ {
+            diff[0] = self.read_mv_component(ctx, 0);
+        }
+        if joint == MV_JOINT_HNZVZ || joint == MV_JOINT_HNZVNZ {
+            diff[1] = self.read_mv_component(ctx, 1);
+        }
+        let pred = self.b.pred_mv[list];
+        let mv = [pred[0] + diff[0], pred[1] + diff[1]];
+        if diff.iter().chain(&mv).any(|&v| v <= MV_LOW || v >= MV_UPP) {
+            self.set_corrupt("AV1 motion vector out of range");
+        }
+        mv
+    }
+
+    fn read_mv_component(&mut self, ctx: usize, comp: usize) -> i32 {
+        let hdr = self.f.hdr;
+        let cdfs = &mut self.cdf.mv[ctx].comps[comp];
+        let sign = self.sd.read_symbol(&mut cdfs.sign) == 1;
+        let class = self.sd.read_symbol(&mut cdfs.classes);
+        let mag = if class == 0 {
+            let class0_bit = self.sd.read_symbol(&mut cdfs.class0_bit) as i32;
+            let fr = if hdr.force_integer_mv { 3 } else { self.sd.read_symbol(&mut cdfs.class0_fr[class0_bit as usize]) as i32 };
+            let hp = if hdr.allow_high_precision_mv { self.sd.read_symbol(&mut cdfs.class0_hp) as i32 } else { 1 };
+            ((class0_bit << 3) | (fr << 1) | hp) + 1
+        } else {
+            let mut d = 0;
+            for i in 0..class {
+                d |= (self.sd.read_symbol(&mut cdfs.bits[i]) as i32) << i;
+            }
+            let fr = if hdr.force_integer_mv { 3 } else { self.sd.read_symbol(&mut cdfs.fr) as i32 };
+            let hp = if hdr.allow_high_precision_mv { self.sd.read_symbol(&mut cdfs.hp) as i32 } else { 1 };
+            (CLASS0_SIZE << (class + 2)) + ((d << 3) | (fr << 1) | hp) + 1
+        };
+        if sign { -mag } else { mag }
+    }
+
+    /// Collect neighbouring blocks using the same single reference as
+    /// samples for local warp estimation
+    pub(super) fn find_warp_samples(&mut self) {
+        let hdr = self.f.hdr;
+        let (mut do_top_left, mut do_top_right) = (true, true);
+        self.b.num_samples = 0;
+        self.b.num_samples_scanned = 0;
+        let (mi_row, mi_col) = (self.b.mi_row, self.b.mi_col);
+        let (w4, h4) = (self.b.bw4, self.b.bh4);
+        if self.b.avail_u {
+            let src_w = block_width4(self.mi(mi_row - 1, mi_col).mi_size as usize);
+            if w4 <= src_w {
+                let col_offset = -((mi_col & (src_w - 1)) as isize);
+                if col_offset < 0 {
+                    do_top_left = false;
+                }
+                if col_offset + src_w as isize > w4 as isize {
+                    do_top_right = false;
+                }
+                self.add_sample(-1, 0);
+            } else {
+                let mut i = 0;
+                while i < w4.min(hdr.mi_cols - mi_col) {
+                    let step = block_width4(self.mi(mi_row - 1, mi_col + i).mi_size as usize);
+                    self.add_sample(-1, i as isize);
+                    i += step;
+                }
+            }
+        }
+        if self.b.avail_l {
+            let src_h = block_height4(self.mi(mi_row, mi_col - 1).mi_size as usize);
+            if h4 <= src_h {
+                let row_offset = -((mi_row & (src_h - 1)) as isize);
+                if row_offset < 0 {
+                    do_top_left = false;
+                }
+                self.add_sample(0, -1);
+            } else {
+                let mut i = 0;
+                while i < h4.min(hdr.mi_rows - mi_row) {
+                    let step = block_height4(self.mi(mi_row + i, mi_col - 1).mi_size as usize);
+                    self.add_sample(i as isize, -1);
+                    i += step;
+                }
+            }
+        }
+        if do_top_left {
+            self.add_sample(-1, -1);
+        }
+        if do_top_right && w4.max(h4) <= 16 {
+            self.add_sample(-1, w4 as isize);
+        }
+        if self.b.num_samples == 0 && self.b.num_samples_scanned > 0 {
+            self.b.num_samples = 1;
+        }
+    }
+
+    fn add_sample(&mut self, delta_row: isize, delta_col: isize) {
+        if self.b.num_samples_scanned >= LEAST_SQUARES_SAMPLES_MAX {
+            return;
+        }
+        let mv_row = self.b.mi_row as isize + delta_row;
+        let mv_col = self.b.mi_col as isize + delta_col;
+        if !self.is_inside(mv_row, mv_col) || !self.mi_decoded(delta_row, delta_col) {
+            return;
+        }
+        let cand = *self.mi(mv_row as usize, mv_col as usize);
+        if cand.ref_frame[0] != self.b.ref_frame[0] || cand.ref_frame[1] != NONE_FRAME {
+            return;
+        }
+        let (cand_w4, cand_h4) = (block_width4(cand.mi_size as usize), block_height4(cand.mi_size as usize));
+        let cand_row = mv_row as usize & !(cand_h4 - 1);
+        let cand_col = mv_col as usize & !(cand_w4 - 1);
+        let mid_y = (cand_row * 4 + cand_h4 * 2) as i32 - 1;
+        let mid_x = (cand_col * 4 + cand_w4 * 2) as i32 - 1;
+        let threshold = (block_width(self.b.mi_size).max(block_height(self.b.mi_size)) as i32).clamp(16, 112);
+        let cand_mv = self.mi(cand_row, cand_col).mv[0];
+        let mv = self.b.mv[0];
+        let valid = (cand_mv[0] - mv[0]).abs() + (cand_mv[1] - mv[1]).abs() <= threshold;
+        self.b.num_samples_scanned += 1;
+        if !valid && self.b.num_samples_scanned > 1 {
+            return;
+        }
+        self.b.cand_list[self.b.num_samples] = [mid_y * 8, mid_x * 8, mid_y * 8 + cand_mv[0], mid_x * 8 + cand_mv[1]];
+        if valid {
+            self.b.num_samples += 1;
+        }
+    }
+}
+
+const MV_JOINT_HNZVZ: usize = 1;
+const MV_JOINT_HZVNZ: usize = 2;
+const MV_JOINT_HNZVNZ: usize = 3;
+const CLASS0_SIZE: i32 = 2;
+const INTRABC_DELAY_PIXELS: i32 = 256;
+
+/// The single-reference mode of one side of a compound mode
+fn compound_component_mode(y_mode: u8, list: usize) -> u8 {
+    if y_mode < NEAREST_NEARESTMV {
+        return y_mode;
+    }
+    match (list, y_mode) {
+        (0, NEW_NEWMV | NEW_NEARESTMV | NEW_NEARMV) | (1, NEW_NEWMV | NEAREST_NEWMV | NEAR_NEWMV) => NEWMV,
+        (0, NEAREST_NEARESTMV | NEAREST_NEWMV) | (1, NEAREST_NEARESTMV | NEW_NEARESTMV) => NEARESTMV,
+        (0, NEAR_NEARMV | NEAR_NEWMV) | (1, NEAR_NEARMV | NEW_NEARMV) => NEARMV,
+        _ => GLOBALMV,
+    }
+}
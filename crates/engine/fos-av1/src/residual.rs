@@ -0,0 +1,541 @@
+//! AV1 Residual Decoding
+//!
+//! Transform block layout, coefficient reading with its neighbour contexts
+//! and dequantization of one block.
+
+use super::tables::*;
+use super::tile::TileDecoder;
+use super::transform::inverse_transform_add;
+
+const NUM_BASE_LEVELS: i32 = 2;
+const COEFF_BASE_RANGE: i32 = 12;
+const BR_CDF_SIZE: i32 = 4;
+
+/// Neighbour positions feeding the base level context, `[row, col]`
+const SIG_REF_DIFF_OFFSET: [[[usize; 2]; 5]; 3] = [
+    [[0, 1], [1, 0], [1, 1], [0, 2], [2, 0]],
+    [[0, 1], [1, 0], [0, 2], [0, 3], [0, 4]],
+    [[0, 1], [1, 0], [2, 0], [3, 0], [4, 0]],
+];
+
+/// Neighbour positions feeding the level range context, `[row, col]`
+const MAG_REF_OFFSET: [[[usize; 2]; 3]; 3] = [
+    [[0, 1], [1, 0], [1, 1]],
+    [[0, 1], [1, 0], [0, 2]],
+    [[0, 1], [1, 0], [2, 0]],
+];
+
+const COEFF_BASE_POS_CTX_OFFSET: [usize; 3] = [26, 31, 36];
+
+const FILTER_INTRA_MODE_TO_INTRA_DIR: [u8; 5] = [DC_PRED, V_PRED, H_PRED, D157_PRED, DC_PRED];
+
+/// Transform size whose coefficients are coded: 64 sample sides keep 32
+fn adjusted_tx_size(tx: usize) -> usize {
+    tx_size(TX_WIDTH_LOG2[tx].min(5), TX_HEIGHT_LOG2[tx].min(5))
+}
+
+fn find_tx_size(w: usize, h: usize) -> usize {
+    tx_size(w.trailing_zeros() as u8, h.trailing_zeros() as u8)
+}
+
+fn in_tx_set(set: usize, is_inter: bool, tx_type: u8) -> bool {
+    let types: &[u8] = match (is_inter, set) {
+        (_, TX_SET_DCTONLY) => &[DCT_DCT],
+        (false, TX_SET_INTRA_1) => &INTRA_SET1_TX_TYPES,
+        (false, _) => &INTRA_SET2_TX_TYPES,
+        (true, TX_SET_INTER_1) => &INTER_SET1_TX_TYPES,
+        (true, TX_SET_INTER_2) => &INTER_SET2_TX_TYPES,
+        (true, _) => &INTER_SET3_TX_TYPES,
+    };
+    types.contains(&tx_type)
+}
+
+impl TileDecoder<'_> {
+    /// Decode the residual of the current block, predicting intra blocks
+    /// transform block by transform block
+    pub(super) fn residual(&mut self) {
+        let f = self.f;
+        let b = &self.b;
+        let (mi_row, mi_col, mi_size, lossless, is_inter) = (b.mi_row, b.mi_col, b.mi_size, b.lossless, b.is_inter);
+        let width_chunks = (b.bw4 >> 4).max(1);
+        let height_chunks = (b.bh4 >> 4).max(1);
+        let planes = 1 + 2 * b.has_chroma as usize;
+        for chunk_y in 0..height_chunks {
+            for chunk_x in 0..width_chunks {
+                let mi_row_chunk = mi_row + (chunk_y << 4);
+                let mi_col_chunk = mi_col + (chunk_x << 4);
+                for plane in 0..planes {
+                    let tx = if lossless { TX_4X4 } else { self.get_tx_size(plane, self.b.tx_size) };
+                    let (step_x, step_y) = (tx_width(tx) >> 2, tx_height(tx) >> 2);
+                    let (sx, sy) = f.sub(plane);
+                    let plane_size = if plane == 0 { mi_size } else { subsampled_size(mi_size, f.ss_x, f.ss_y) };
+                    let (num4x4_w, num4x4_h) = (block_width4(plane_size), block_height4(plane_size));
+                    if is_inter && !lossless && plane == 0 {
+                        let (base_x, base_y) = (mi_col_chunk * 4, mi_row_chunk * 4);
+                        self.transform_tree(base_x, base_y, num4x4_w.min(16) * 4, num4x4_h.min(16) * 4);
+                    } else {
+                        let base_x = (mi_col >> sx) * 4;
+                        let base_y = (mi_row >> sy) * 4;
+                        for y in (0..num4x4_h.min(16 >> sy)).step_by(step_y) {
+                            for x in (0..num4x4_w.min(16 >> sx)).step_by(step_x) {
+                                let x = x + ((chunk_x << 4) >> sx);
+                                let y = y + ((chunk_y << 4) >> sy);
+                                self.transform_block(plane, base_x, base_y, tx, x, y);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Transform size of a plane of the current block
+    fn get_tx_size(&self, plane: usize, tx: usize) -> usize {
+        if plane == 0 {
+            return tx;
+        }
+        let uv_tx = MAX_TX_SIZE_RECT[subsampled_size(self.b.mi_size, self.f.ss_x, self.f.ss_y)];
+        if tx_width(uv_tx) == 64 || tx_height(uv_tx) == 64 {
+            return match (tx_width(uv_tx), tx_height(uv_tx)) {
+                (16, _) => TX_16X32,
+                (_, 16) => TX_32X16,
+                _ => TX_32X32,
+            };
+        }
+        uv_tx
+    }
+
+    /// Split a luma area of an inter block along its coded transform sizes
+    fn transform_tree(&mut self, start_x: usize, start_y: usize, w: usize, h: usize) {
+        let hdr = self.f.hdr;
+        if start_x >= hdr.mi_cols * 4 || start_y >= hdr.mi_rows * 4 {
+            return;
+        }
+        let tx = self.mi(start_y >> 2, start_x >> 2).inter_tx_size as usize;
+        if w <= tx_width(tx) && h <= tx_height(tx) {
+            self.transform_block(0, start_x, start_y, find_tx_size(w, h), 0, 0);
+        } else if w > h {
+            self.transform_tree(start_x, start_y, w / 2, h);
+            self.transform_tree(start_x + w / 2, start_y, w / 2, h);
+        } else if w < h {
+            self.transform_tree(start_x, start_y, w, h / 2);
+            self.transform_tree(start_x, start_y + h / 2, w, h / 2);
+        } else {
+            self.transform_tree(start_x, start_y, w / 2, h / 2);
+            self.transform_tree(start_x + w / 2, start_y, w / 2, h / 2);
+            self.transform_tree(start_x, start_y + h / 2, w / 2, h / 2);
+            self.transform_tree(start_x + w / 2, start_y + h / 2, w / 2, h / 2);
+        }
+    }
+
+    fn transform_block(&mut self, plane: usize, base_x: usize, base_y: usize, tx: usize, x: usize, y: usize) {
+        let f = self.f;
+        let (start_x, start_y) = (base_x + 4 * x, base_y + 4 * y);
+        let (sx, sy) = f.sub(plane);
+        let row = (start_y << sy) >> 2;
+        let col = (start_x << sx) >> 2;
+        let sb_mask = if f.seq.use_128x128_superblock { 31 } else { 15 };
+        let sub_row = ((row & sb_mask) >> sy) as isize;
+        let sub_col = ((col & sb_mask) >> sx) as isize;
+        let (step_x, step_y) = (tx_width(tx) >> 2, tx_height(tx) >> 2);
+        if start_x >= (f.hdr.mi_cols * 4) >> sx || start_y >= (f.hdr.mi_rows * 4) >> sy {
+            return;
+        }
+        let b = &self.b;
+        if !b.is_inter {
+            let palette_size = if plane == 0 { b.palette_size_y } else { b.palette_size_uv };
+            if palette_size > 0 {
+                self.predict_palette(plane, start_x, start_y, x, y, tx);
+            } else {
+                let is_cfl = plane > 0 && b.uv_mode == UV_CFL_PRED;
+                let mode = match plane {
+                    0 => b.y_mode,
+                    _ if is_cfl => DC_PRED,
+                    _ => b.uv_mode,
+                };
+                let have_left = (if plane == 0 { b.avail_l } else { b.avail_l_chroma }) || x > 0;
+                let have_above = (if plane == 0 { b.avail_u } else { b.avail_u_chroma }) || y > 0;
+                let have_above_right = self.block_decoded(plane, sub_row - 1, sub_col + step_x as isize);
+                let have_below_left = self.block_decoded(plane, sub_row + step_y as isize, sub_col - 1);
+                self.predict_intra(
+                    plane,
+                    start_x,
+                    start_y,
+                    have_left,
+                    have_above,
+                    have_above_right,
+                    have_below_left,
+                    mode,
+                    TX_WIDTH_LOG2[tx],
+                    TX_HEIGHT_LOG2[tx],
+                );
+                if is_cfl {
+                    self.predict_chroma_from_luma(plane, start_x, start_y, tx);
+                }
+            }
+            if plane == 0 {
+                self.b.max_luma_w = start_x + step_x * 4;
+                self.b.max_luma_h = start_y + step_y * 4;
+            }
+        }
+        if !self.b.skip {
+            let mut quant = [0i32; 1024];
+            let (eob, tx_type) = self.coeffs(plane, start_x, start_y, tx, &mut quant);
+            if eob > 0 {
+                self.reconstruct(plane, start_x, start_y, tx, tx_type, &mut quant);
+            }
+        }
+        self.out.lf_tx[plane].fill(row >> sy, col >> sx, step_y, step_x, tx as u8);
+        for i in 0..step_y as isize {
+            for j in 0..step_x as isize {
+                self.block_decoded[plane][(sub_row + i + 1) as usize][(sub_col + j + 1) as usize] = true;
+            }
+        }
+    }
+
+    /// Read the quantized coefficients of a transform block into `quant`,
+    /// returning the end of block position and the transform type
+    fn coeffs(&mut self, plane: usize, start_x: usize, start_y: usize, tx: usize, quant: &mut [i32; 1024]) -> (usize, u8) {
+        let f = self.f;
+        let (sx, sy) = f.sub(plane);
+        let (x4, y4) = (start_x >> 2, start_y >> 2);
+        let (w4, h4) = (tx_width(tx) >> 2, tx_height(tx) >> 2);
+        let tx_sz_ctx = (TX_SIZE_SQR[tx] + TX_SIZE_SQR_UP[tx] + 1) >> 1;
+        let ptype = (plane > 0) as usize;
+        let seg_eob = if tx == TX_16X64 || tx == TX_64X16 { 512 } else { (tx_width(tx) * tx_height(tx)).min(1024) };
+        quant[..seg_eob].fill(0);
+        let (max_x4, max_y4) = (f.hdr.mi_cols >> sx, f.hdr.mi_rows >> sy);
+
+        let ctx = self.all_zero_ctx(plane, tx, x4, y4, w4, h4, max_x4, max_y4);
+        let all_zero = self.sd.read_symbol(&mut self.cdf.coeff.txb_skip[tx_sz_ctx][ctx]) == 1;
+        let mut eob = 0;
+        let mut cul_level = 0u32;
+        let mut dc_category = 0u8;
+        let mut tx_type = DCT_DCT;
+        if all_zero {
+            if plane == 0 {
+                self.set_tx_types(x4, y4, w4, h4, DCT_DCT);
+            }
+        } else {
+            if plane == 0 {
+                self.read_transform_type(x4, y4, tx);
+            }
+            tx_type = self.compute_tx_type(plane, tx, x4, y4);
+            let class = tx_class(tx_type);
+            let scan = scan(tx, tx_type);
+            eob = self.read_eob(tx, tx_sz_ctx, ptype, class);
+
+            let adj = adjusted_tx_size(tx);
+            let bwl = TX_WIDTH_LOG2[adj] as usize;
+            let txh = tx_height(adj);
+            for c in (0..eob).rev() {
+                let pos = scan[c] as usize;
+                let mut level = if c == eob - 1 {
+                    let area = txh << bwl;
+                    let ctx = match c {
+                        0 => 0,
+                        _ if c <= area / 8 => 1,
+                        _ if c <= area / 4 => 2,
+                        _ => 3,
+                    };
+                    self.sd.read_symbol(&mut self.cdf.coeff.coeff_base_eob[tx_sz_ctx][ptype][ctx]) as i32 + 1
+                } else {
+                    let ctx = coeff_base_ctx(quant, tx, class, bwl, txh, pos);
+                    self.sd.read_symbol(&mut self.cdf.coeff.coeff_base[tx_sz_ctx][ptype][ctx]) as i32
+                };
+                if level > NUM_BASE_LEVELS {
+                    let ctx = coeff_br_ctx(quant, class, bwl, txh, pos);
+                    for _ in 0..COEFF_BASE_RANGE / (BR_CDF_SIZE - 1) {
+                        let br = self.sd.read_symbol(&mut self.cdf.coeff.coeff_br[tx_sz_ctx.min(TX_32X32)][ptype][ctx]) as i32;
+                        level += br;
+                        if br < BR_CDF_SIZE - 1 {
+                            break;
+                        }
+                    }
+                }
+                quant[pos] = level;
+            }
+
+            for c in 0..eob {
+                let pos = scan[c] as usize;
+                let sign = if quant[pos] == 0 {
+                    false
+                } else if c == 0 {
+                    let ctx = self.dc_sign_ctx(plane, x4, y4, w4, h4, max_x4, max_y4);
+                    self.sd.read_symbol(&mut self.cdf.coeff.dc_sign[ptype][ctx]) == 1
+                } else {
+                    self.sd.read_literal(1) == 1
+                };
+                if quant[pos] > NUM_BASE_LEVELS + COEFF_BASE_RANGE {
+                    quant[pos] = (self.sd.read_golomb() as i32).saturating_add(COEFF_BASE_RANGE + NUM_BASE_LEVELS + 1);
+                }
+                if pos == 0 && quant[pos] > 0 {
+                    dc_category = if sign { 1 } else { 2 };
+                }
+                quant[pos] &= 0xFFFFF;
+                cul_level += quant[pos] as u32;
+                if sign {
+                    quant[pos] = -quant[pos];
+                }
+            }
+            cul_level = cul_level.min(63);
+        }
+        self.above_level[plane][x4..x4 + w4].fill(cul_level as u8);
+        self.above_dc[plane][x4..x4 + w4].fill(dc_category);
+        self.left_level[plane][y4..y4 + h4].fill(cul_level as u8);
+        self.left_dc[plane][y4..y4 + h4].fill(dc_category);
+        (eob, tx_type)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn all_zero_ctx(&self, plane: usize, tx: usize, x4: usize, y4: usize, w4: usize, h4: usize, max_x4: usize, max_y4: usize) -> usize {
+        let (w, h) = (tx_width(tx), tx_height(tx));
+        let bsize = if plane == 0 { self.b.mi_size } else { subsampled_size(self.b.mi_size, self.f.ss_x, self.f.ss_y) };
+        let above = (x4..(x4 + w4).min(max_x4)).map(|i| (self.above_level[plane][i], self.above_dc[plane][i]));
+        let left = (y4..(y4 + h4).min(max_y4)).map(|i| (self.left_level[plane][i], self.left_dc[plane][i]));
+        if plane == 0 {
+            let top = above.map(|(l, _)| l).max().unwrap_or(0);
+            let left = left.map(|(l, _)| l).max().unwrap_or(0);
+            let (lo, hi) = (top.min(left), top.max(left));
+            if block_width(bsize) == w && block_height(bsize) == h {
+                0
+            } else if hi == 0 {
+                1
+            } else if lo == 0 {
+                2 + (hi > 3) as usize
+            } else if hi <= 3 {
+                4
+            } else if lo <= 3 {
+                5
+            } else {
+                6
+            }
+        } else {
+            let above = above.fold(0, |acc, (l, d)| acc | l | d);
+            let left = left.fold(0, |acc, (l, d)| acc | l | d);
+            let ctx = 7 + (above != 0) as usize + (left != 0) as usize;
+            ctx + if block_width(bsize) * block_height(bsize) > w * h { 3 } else { 0 }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn dc_sign_ctx(&self, plane: usize, x4: usize, y4: usize, w4: usize, h4: usize, max_x4: usize, max_y4: usize) -> usize {
+        let above = &self.above_dc[plane][x4..(x4 + w4).min(max_x4).max(x4)];
+        let left = &self.left_dc[plane][y4..(y4 + h4).min(max_y4).max(y4)];
+        let dc_sign: i32 = above
+            .iter()
+            .chain(left)
+            .map(|&s| match s {
+                1 => -1,
+                2 => 1,
+                _ => 0,
+            })
+            .sum();
+        match dc_sign {
+            s if s < 0 => 1,
+            s if s > 0 => 2,
+            _ => 0,
+        }
+    }
+
+    fn read_eob(&mut self, tx: usize, tx_sz_ctx: usize, ptype: usize, class: u8) -> usize {
+        let multisize = TX_WIDTH_LOG2[tx].min(5) + TX_HEIGHT_LOG2[tx].min(5) - 4;
+        let ctx = (class != TX_CLASS_2D) as usize;
+        let coeff = &mut self.cdf.coeff;
+        let eob_pt = 1 + match multisize {
+            0 => self.sd.read_symbol(&mut coeff.eob_pt_16[ptype][ctx]),
+            1 => self.sd.read_symbol(&mut coeff.eob_pt_32[ptype][ctx]),
+            2 => self.sd.read_symbol(&mut coeff.eob_pt_64[ptype][ctx]),
+            3 => self.sd.read_symbol(&mut coeff.eob_pt_128[ptype][ctx]),
+            4 => self.sd.read_symbol(&mut coeff.eob_pt_256[ptype][ctx]),
+            5 => self.sd.read_symbol(&mut coeff.eob_pt_512[ptype][ctx]),
+            _ => self.sd.read_symbol(&mut coeff.eob_pt_1024[ptype][ctx]),
+        };
+        if eob_pt < 2 {
+            return eob_pt;
+        }
+        let mut eob = (1 << (eob_pt - 2)) + 1;
+        if eob_pt >= 3 {
+            if self.sd.read_symbol(&mut coeff.eob_extra[tx_sz_ctx][ptype][eob_pt - 3]) == 1 {
+                eob += 1 << (eob_pt - 3);
+            }
+            for i in 1..eob_pt - 2 {
+                if self.sd.read_literal(1) == 1 {
+                    eob += 1 << (eob_pt - 3 - i);
+                }
+            }
+        }
+        eob
+    }
+
+    fn set_tx_types(&mut self, x4: usize, y4: usize, w4: usize, h4: usize, tx_type: u8) {
+        let mi = &mut self.out.mi;
+        for row in y4..(y4 + h4).min(mi.row0 + mi.rows) {
+            for col in x4..(x4 + w4).min(mi.col0 + mi.cols) {
+                mi.get_mut(row, col).tx_type = tx_type;
+            }
+        }
+    }
+
+    fn get_tx_set(&self, tx: usize) -> usize {
+        let (sqr, sqr_up) = (TX_SIZE_SQR[tx], TX_SIZE_SQR_UP[tx]);
+        if sqr_up > TX_32X32 {
+            return TX_SET_DCTONLY;
+        }
+        let reduced = self.f.hdr.reduced_tx_set;
+        if self.b.is_inter {
+            if reduced || sqr_up == TX_32X32 {
+                TX_SET_INTER_3
+            } else if sqr == TX_16X16 {
+                TX_SET_INTER_2
+            } else {
+                TX_SET_INTER_1
+            }
+        } else if sqr_up == TX_32X32 {
+            TX_SET_DCTONLY
+        } else if reduced || sqr == TX_16X16 {
+            TX_SET_INTRA_2
+        } else {
+            TX_SET_INTRA_1
+        }
+    }
+
+    fn read_transform_type(&mut self, x4: usize, y4: usize, tx: usize) {
+        let set = self.get_tx_set(tx);
+        let qidx = self.f.hdr.qindex(self.b.segment_id as usize, None);
+        let sqr = TX_SIZE_SQR[tx];
+        let tx_type = if set > 0 && qidx > 0 {
+            let b = &self.b;
+            let cdf = &mut self.cdf;
+            if b.is_inter {
+                match set {
+                    TX_SET_INTER_1 => INTER_SET1_TX_TYPES[self.sd.read_symbol(&mut cdf.inter_tx_set1[sqr])],
+                    TX_SET_INTER_2 => INTER_SET2_TX_TYPES[self.sd.read_symbol(&mut cdf.inter_tx_set2[sqr])],
+                    _ => INTER_SET3_TX_TYPES[self.sd.read_symbol(&mut cdf.inter_tx_set3[sqr])],
+                }
+            } else {
+                let dir = if b.use_filter_intra {
+                    FILTER_INTRA_MODE_TO_INTRA_DIR[b.filter_intra_mode as usize]
+                } else {
+                    b.y_mode
+                } as usize;
+                match set {
+                    TX_SET_INTRA_1 => INTRA_SET1_TX_TYPES[self.sd.read_symbol(&mut cdf.intra_tx_set1[sqr][dir])],
+                    _ => INTRA_SET2_TX_TYPES[self.sd.read_symbol(&mut cdf.intra_tx_set2[sqr][dir])],
+                }
+            }
+        } else {
+            DCT_DCT
+        };
+        self.set_tx_types(x4, y4, tx_width(tx) >> 2, tx_height(tx) >> 2, tx_type);
+    }
+
+    fn compute_tx_type(&self, plane: usize, tx: usize, block_x: usize, block_y: usize) -> u8 {
+        let b = &self.b;
+        if b.lossless || TX_SIZE_SQR_UP[tx] > TX_32X32 {
+            return DCT_DCT;
+        }
+        if plane == 0 {
+            return self.mi(block_y, block_x).tx_type;
+        }
+        let set = self.get_tx_set(tx);
+        let tx_type = if b.is_inter {
+            let (sx, sy) = self.f.sub(plane);
+            let x4 = b.mi_col.max(block_x << sx);
+            let y4 = b.mi_row.max(block_y << sy);
+            self.mi(y4, x4).tx_type
+        } else {
+            MODE_TO_TXFM[b.uv_mode as usize]
+        };
+        if in_tx_set(set, b.is_inter, tx_type) { tx_type } else { DCT_DCT }
+    }
+
+    /// Dequantize the coefficients of a transform block and add the inverse
+    /// transform to the prediction
+    fn reconstruct(&mut self, plane: usize, start_x: usize, start_y: usize, tx: usize, tx_type: u8, quant: &mut [i32; 1024]) {
+        let f = self.f;
+        let hdr = f.hdr;
+        let qidx = hdr.qindex(self.b.segment_id as usize, Some(self.current_q_index));
+        let (dc_delta, ac_delta) = match plane {
+            0 => (hdr.delta_q_y_dc, 0),
+            1 => (hdr.delta_q_u_dc, hdr.delta_q_u_ac),
+            _ => (hdr.delta_q_v_dc, hdr.delta_q_v_ac),
+        };
+        let dc_q = dc_q(f.bit_depth, qidx + dc_delta) as i64;
+        let ac_q = ac_q(f.bit_depth, qidx + ac_delta) as i64;
+        let area = tx_width(tx) * tx_height(tx);
+        let shift = (area > 256) as u32 + (area > 1024) as u32;
+        let max = (1i64 << (7 + f.bit_depth)) - 1;
+        let n = tx_width(tx).min(32) * tx_height(tx).min(32);
+        for (i, q) in quant[..n].iter_mut().enumerate() {
+            if *q == 0 {
+                continue;
+            }
+            let step = if i == 0 { dc_q } else { ac_q };
+            let dq = (((*q as i64).abs() * step) & 0xFFFFFF) >> shift;
+            *q = (if *q < 0 { -dq } else { dq }).clamp(-max - 1, max) as i32;
+        }
+        let lossless = self.b.lossless;
+        inverse_transform_add(&quant[..n], tx, tx_type, lossless, f.bit_depth, &mut self.out.planes[plane], start_x, start_y);
+    }
+}
+
+fn coeff_base_ctx(quant: &[i32], tx: usize, class: u8, bwl: usize, txh: usize, pos: usize) -> usize {
+    let (row, col) = (pos >> bwl, pos & ((1 << bwl) - 1));
+    let mag: i32 = SIG_REF_DIFF_OFFSET[class as usize]
+        .iter()
+        .map(|&[dr, dc]| (row + dr, col + dc))
+        .filter(|&(r, c)| r < txh && c < 1 << bwl)
+        .map(|(r, c)| quant[(r << bwl) + c].abs().min(3))
+        .sum();
+    let ctx = (((mag + 1) >> 1) as usize).min(4);
+    match class {
+        TX_CLASS_2D if pos == 0 => 0,
+        TX_CLASS_2D => ctx + COEFF_BASE_CTX_OFFSET[tx][row.min(4)][col.min(4)] as usize,
+        TX_CLASS_VERT => ctx + COEFF_BASE_POS_CTX_OFFSET[row.min(2)],
+        _ => ctx + COEFF_BASE_POS_CTX_OFFSET[col.min(2)],
+    }
+}
+
+fn coeff_br_ctx(quant: &[i32], class: u8, bwl: usize, txh: usize, pos: usize) -> usize {
+    let (row, col) = (pos >> bwl, pos & ((1 << bwl) - 1));
+    let mag: i32 = MAG_REF_OFFSET[class as usize]
+        .iter()
+        .map(|&[dr, dc]| (row + dr, col + dc))
+        .filter(|&(r, c)| r < txh && c < 1 << bwl)
+        .map(|(r, c)| quant[(r << bwl) + c].min(COEFF_BASE_RANGE + NUM_BASE_LEVELS + 1))
+        .sum();
+    let mag = (((mag + 1) >> 1) as usize).min(6);
+    let near = match class {
+        _ if pos == 0 => return mag,
+        TX_CLASS_2D => row < 2 && col < 2,
+        TX_CLASS_HORIZ => col == 0,
+        _ => row == 0,
+    };
+    mag + if near { 7 } else { 14 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjusted_tx_size() {
+        assert_eq!(adjusted_tx_size(TX_64X64), TX_32X32);
+        assert_eq!(adjusted_tx_size(TX_16X64), TX_16X32);
+        assert_eq!(adjusted_tx_size(TX_64X16), TX_32X16);
+        assert_eq!(adjusted_tx_size(TX_8X16), TX_8X16);
+        assert_eq!(find_tx_size(32, 8), TX_32X8);
+    }
+
+    #[test]
+    fn test_coeff_contexts() {
+        let mut quant = [0i32; 16];
+        // Right and below neighbours of the DC position
+        quant[1] = 5;
+        quant[4] = 1;
+        assert_eq!(coeff_base_ctx(&quant, TX_4X4, TX_CLASS_2D, 2, 4, 0), 0);
+        assert_eq!(coeff_br_ctx(&quant, TX_CLASS_2D, 2, 4, 0), 3);
+        assert_eq!(coeff_base_ctx(&quant, TX_4X4, TX_CLASS_VERT, 2, 4, 0), 2 + 26);
+    }
+}
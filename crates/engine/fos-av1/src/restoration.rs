@@ -0,0 +1,286 @@
+//! AV1 Super-resolution and Loop Restoration
+//!
+//! Upscales frames coded at a reduced width back to their full width, then
+//! runs the Wiener or self-guided filter chosen for each restoration unit.
+//! Restoration works in 64 row stripes offset 8 rows upwards; rows outside
+//! the current stripe come from the deblocked frame rather than the CDEF
+//! output, as the hardware line buffers would hold them.
+
+use super::headers::{RESTORE_NONE, RESTORE_SGRPROJ, RESTORE_WIENER};
+use super::state::{Grid, PlaneBuf};
+use super::tables::{SGR_PARAMS, UPSCALE_FILTER};
+use super::tile::{FrameContext, LrUnit};
+
+const SUPERRES_SCALE_BITS: i64 = 14;
+const SUPERRES_SCALE_MASK: i64 = (1 << SUPERRES_SCALE_BITS) - 1;
+const SUPERRES_EXTRA_BITS: i64 = 8;
+const SUPERRES_FILTER_OFFSET: i64 = 3;
+
+const SGRPROJ_RST_BITS: u32 = 4;
+const SGRPROJ_PRJ_BITS: u32 = 7;
+const SGRPROJ_MTABLE_BITS: u32 = 20;
+const SGRPROJ_SGR_BITS: u32 = 8;
+const SGRPROJ_RECIP_BITS: u32 = 12;
+
+/// Upscale the planes of a frame coded with super-resolution to the
+/// upscaled width
+pub(super) fn superres_upscale(f: &FrameContext, planes: &[PlaneBuf]) -> Vec<PlaneBuf> {
+    let hdr = f.hdr;
+    let max_val = (1 << f.bit_depth) - 1;
+    planes
+        .iter()
+        .enumerate()
+        .map(|(plane, src)| {
+            let (sx, sy) = f.sub(plane);
+            let down_w = ((hdr.frame_width as usize + sx) >> sx) as i64;
+            let up_w = ((hdr.upscaled_width as usize + sx) >> sx) as i64;
+            let plane_h = (hdr.frame_height as usize + sy) >> sy;
+            let step = ((down_w << SUPERRES_SCALE_BITS) + up_w / 2) / up_w;
+            let err = up_w * step - (down_w << SUPERRES_SCALE_BITS);
+            let initial = ((-((up_w - down_w) << (SUPERRES_SCALE_BITS - 1)) + up_w / 2) / up_w
+                + (1 << (SUPERRES_EXTRA_BITS - 1))
+                - err / 2)
+                & SUPERRES_SCALE_MASK;
+            let max_x = ((hdr.mi_cols >> sx) * 4) as i64 - 1;
+            let cols = (hdr.upscaled_width as usize).next_multiple_of(8) >> sx;
+            let mut out = Grid::new(0, 0, src.rows, cols, 0);
+            for y in 0..plane_h {
+                for x in 0..up_w {
+                    let src_x = -(1 << SUPERRES_SCALE_BITS) + initial + x * step;
+                    let px = src_x >> SUPERRES_SCALE_BITS;
+                    let filter = &UPSCALE_FILTER[((src_x & SUPERRES_SCALE_MASK) >> SUPERRES_EXTRA_BITS) as usize];
+                    let sum: i32 = (0..8)
+                        .map(|k| {
+                            let sample_x = (px + k as i64 - SUPERRES_FILTER_OFFSET).clamp(0, max_x) as usize;
+                            *src.get(y, sample_x) as i32 * filter[k]
+                        })
+                        .sum();
+                    *out.get_mut(y, x as usize) = ((sum + 64) >> 7).clamp(0, max_val) as u16;
+                }
+            }
+            out
+        })
+        .collect()
+}
+
+/// Sources of one plane for the restoration filters
+struct LrPlane<'a> {
+    cdef: &'a PlaneBuf,
+    deblocked: &'a PlaneBuf,
+    end_x: isize,
+    end_y: isize,
+    bit_depth: u32,
+    /// First and last row of the current stripe
+    stripe_start: isize,
+    stripe_end: isize,
+}
+
+impl LrPlane<'_> {
+    fn sample(&self, x: isize, y: isize) -> i32 {
+        let x = x.clamp(0, self.end_x) as usize;
+        let y = y.clamp(0, self.end_y);
+        if y < self.stripe_start {
+            *self.deblocked.get((self.stripe_start - 2).max(y) as usize, x) as i32
+        } else if y > self.stripe_end {
+            *self.deblocked.get((self.stripe_end + 2).min(y) as usize, x) as i32
+        } else {
+            *self.cdef.get(y as usize, x) as i32
+        }
+    }
+
+    fn clip(&self, v: i32) -> u16 {
+        v.clamp(0, (1 << self.bit_depth) - 1) as u16
+    }
+
+    fn wiener(&self, out: &mut PlaneBuf, unit: &LrUnit, x: usize, y: usize, w: usize, h: usize) {
+        let bd = self.bit_depth;
+        let (round0, round1) = if bd == 12 { (5, 9) } else { (3, 11) };
+        let taps = |c: &[i32; 3]| [c[0], c[1], c[2], 128 - 2 * (c[0] + c[1] + c[2]), c[2], c[1], c[0]];
+        let (vfilter, hfilter) = (taps(&unit.wiener[0]), taps(&unit.wiener[1]));
+        let offset = 1 << (bd + 7 - round0 - 1);
+        let limit = (1 << (bd + 1 + 7 - round0)) - 1;
+        let mut inter = [[0i32; 4]; 10];
+        for (r, row) in inter.iter_mut().enumerate().take(h + 6) {
+            for (c, v) in row.iter_mut().enumerate().take(w) {
+                let s: i32 = (0..7)
+                    .map(|t| hfilter[t] * self.sample((x + c + t) as isize - 3, (y + r) as isize - 3))
+                    .sum();
+                *v = ((s + (1 << (round0 - 1))) >> round0).clamp(-offset, limit - offset);
+            }
+        }
+        for r in 0..h {
+            for c in 0..w {
+                let s: i32 = vfilter.iter().zip(&inter[r..r + 7]).map(|(t, row)| t * row[c]).sum();
+                *out.get_mut(y + r, x + c) = self.clip((s + (1 << (round1 - 1))) >> round1);
+            }
+        }
+    }
+
+    fn self_guided(&self, out: &mut PlaneBuf, unit: &LrUnit, x: usize, y: usize, w: usize, h: usize) {
+        let params = SGR_PARAMS[unit.sgr_set as usize];
+        let mut flt = [[[0i32; 4]; 4]; 2];
+        for (pass, f) in flt.iter_mut().enumerate() {
+            if params[pass * 2] != 0 {
+                self.box_filter(f, x, y, w, h, params[pass * 2], params[pass * 2 + 1] as u32, pass);
+            }
+        }
+        let (w0, w1) = (unit.sgr_xqd[0], unit.sgr_xqd[1]);
+        let w2 = (1 << SGRPROJ_PRJ_BITS) - w0 - w1;
+        let bits = SGRPROJ_RST_BITS + SGRPROJ_PRJ_BITS;
+        for (i, (flt0, flt1)) in flt[0].iter().zip(&flt[1]).enumerate().take(h) {
+            for j in 0..w {
+                let u = (*self.cdef.get(y + i, x + j) as i32) << SGRPROJ_RST_BITS;
+                let mut v = w1 * u;
+                v += w0 * if params[0] != 0 { flt0[j] } else { u };
+                v += w2 * if params[2] != 0 { flt1[j] } else { u };
+                *out.get_mut(y + i, x + j) = self.clip((v + (1 << (bits - 1))) >> bits);
+            }
+        }
+    }
+
+    /// One self-guided filter pass over a block of at most 4x4 samples
+    #[allow(clippy::too_many_arguments)]
+    fn box_filter(&self, flt: &mut [[i32; 4]; 4], x: usize, y: usize, w: usize, h: usize, r: i32, s: u32, pass: usize) {
+        let shift = self.bit_depth - 8;
+        let n = ((2 * r + 1) * (2 * r + 1)) as u32;
+        let one_over_n = ((1 << SGRPROJ_RECIP_BITS) + n / 2) / n;
+        let mut a_buf = [[0u32; 6]; 6];
+        let mut b_buf = [[0u32; 6]; 6];
+        for i in 0..h + 2 {
+            for j in 0..w + 2 {
+                let (mut a, mut b) = (0u32, 0u32);
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        let c = self.sample((x + j) as isize - 1 + dx as isize, (y + i) as isize - 1 + dy as isize) as u32;
+                        a += c * c;
+                        b += c;
+                    }
+                }
+                let a = (a + ((1 << (2 * shift)) >> 1)) >> (2 * shift);
+                let d = (b + ((1 << shift) >> 1)) >> shift;
+                let p = (a * n).saturating_sub(d * d);
+                let z = p.wrapping_mul(s).wrapping_add(1 << (SGRPROJ_MTABLE_BITS - 1)) >> SGRPROJ_MTABLE_BITS;
+                let a2 = if z >= 255 {
+                    256
+                } else if z == 0 {
+                    1
+                } else {
+                    ((z << SGRPROJ_SGR_BITS) + z / 2) / (z + 1)
+                };
+                let b2 = ((1 << SGRPROJ_SGR_BITS) - a2).wrapping_mul(b).wrapping_mul(one_over_n);
+                a_buf[i][j] = a2;
+                b_buf[i][j] = b2.wrapping_add(1 << (SGRPROJ_RECIP_BITS - 1)) >> SGRPROJ_RECIP_BITS;
+            }
+        }
+        for i in 0..h {
+            let shift = if pass == 0 && i & 1 == 1 { 4 } else { 5 };
+            for j in 0..w {
+                let (mut a, mut b) = (0u32, 0u32);
+                for dy in 0..3 {
+                    for dx in 0..3 {
+                        let weight = if pass == 0 {
+                            // Only odd rows carry coefficients in the radius 2 pass
+                            if (i + dy + 1) & 1 == 1 {
+                                if dx == 1 { 6 } else { 5 }
+                            } else {
+                                0
+                            }
+                        } else if dx == 1 || dy == 1 {
+                            4
+                        } else {
+                            3
+                        };
+                        a += weight * a_buf[i + dy][j + dx];
+                        b += weight * b_buf[i + dy][j + dx];
+                    }
+                }
+                let v = a * *self.cdef.get(y + i, x + j) as u32 + b;
+                let bits = SGRPROJ_SGR_BITS + shift - SGRPROJ_RST_BITS;
+                flt[i][j] = ((v + (1 << (bits - 1))) >> bits) as i32;
+            }
+        }
+    }
+}
+
+/// Apply loop restoration. `cdef` and `deblocked` are the upscaled frames
+/// after and before CDEF; `units` holds the restoration units of each
+/// plane.
+pub(super) fn loop_restoration(
+    f: &FrameContext,
+    cdef: &[PlaneBuf],
+    deblocked: &[PlaneBuf],
+    units: &[Grid<LrUnit>],
+) -> Vec<PlaneBuf> {
+    let hdr = f.hdr;
+    let mut out = cdef.to_vec();
+    for plane in 0..f.num_planes {
+        if hdr.frame_restoration_type[plane] == RESTORE_NONE {
+            continue;
+        }
+        let (sx, sy) = f.sub(plane);
+        let unit_size = hdr.loop_restoration_size[plane];
+        let grid = &units[plane];
+        let mut lr = LrPlane {
+            cdef: &cdef[plane],
+            deblocked: &deblocked[plane],
+            end_x: ((hdr.upscaled_width as usize + sx) >> sx) as isize - 1,
+            end_y: ((hdr.frame_height as usize + sy) >> sy) as isize - 1,
+            bit_depth: f.bit_depth as u32,
+            stripe_start: 0,
+            stripe_end: 0,
+        };
+        for y in (0..hdr.frame_height as usize).step_by(4) {
+            let stripe_num = (y + 8) / 64;
+            lr.stripe_start = (stripe_num as isize * 64 - 8) >> sy;
+            lr.stripe_end = lr.stripe_start + (64 >> sy) - 1;
+            let unit_row = (grid.rows - 1).min(((y + 8) >> sy) / unit_size);
+            let py = y >> sy;
+            let h = (4 >> sy).min((lr.end_y + 1) as usize - py);
+            for x in (0..hdr.upscaled_width as usize).step_by(4) {
+                let unit_col = (grid.cols - 1).min((x >> sx) / unit_size);
+                let px = x >> sx;
+                let w = (4 >> sx).min((lr.end_x + 1) as usize - px);
+                let unit = grid.get(unit_row, unit_col);
+                match unit.kind {
+                    RESTORE_WIENER => lr.wiener(&mut out[plane], unit, px, py, w, h),
+                    RESTORE_SGRPROJ => lr.self_guided(&mut out[plane], unit, px, py, w, h),
+                    _ => {}
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upscale_filter_taps_sum_to_unity() {
+        for taps in UPSCALE_FILTER.iter() {
+            assert_eq!(taps.iter().sum::<i32>(), 128);
+        }
+    }
+
+    #[test]
+    fn test_wiener_identity_keeps_samples() {
+        let mut cdef = PlaneBuf::new(0, 0, 8, 8, 0);
+        for (i, v) in cdef.data.iter_mut().enumerate() {
+            *v = (i * 3 % 200) as u16;
+        }
+        let lr = LrPlane {
+            cdef: &cdef,
+            deblocked: &cdef,
+            end_x: 7,
+            end_y: 7,
+            bit_depth: 8,
+            stripe_start: -8,
+            stripe_end: 55,
+        };
+        let mut out = cdef.clone();
+        let unit = LrUnit { kind: RESTORE_WIENER, ..Default::default() };
+        lr.wiener(&mut out, &unit, 2, 2, 4, 4);
+        assert_eq!(out.data, cdef.data);
+    }
+}
@@ -0,0 +1,110 @@
+//! AV1 Frame State
+//!
+//! Sample planes, per-4x4 mode info grids and the reference frame record
+//! shared between the decoding stages.
+
+use super::cdf::CdfContext;
+use super::headers::FrameHeader;
+use std::sync::Arc;
+
+/// Motion vector in 1/8 sample units, `[row, col]`
+pub(super) type Mv = [i32; 2];
+
+/// A rectangle of values addressed with frame coordinates
+#[derive(Debug, Clone)]
+pub(super) struct Grid<T> {
+    pub data: Vec<T>,
+    pub row0: usize,
+    pub col0: usize,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(row0: usize, col0: usize, rows: usize, cols: usize, fill: T) -> Self {
+        Self { data: vec![fill; rows * cols], row0, col0, rows, cols }
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> &T {
+        &self.data[(row - self.row0) * self.cols + col - self.col0]
+    }
+
+    pub fn get_mut(&mut self, row: usize, col: usize) -> &mut T {
+        &mut self.data[(row - self.row0) * self.cols + col - self.col0]
+    }
+
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        row >= self.row0 && col >= self.col0 && row < self.row0 + self.rows && col < self.col0 + self.cols
+    }
+
+    /// Set the cells of a rectangle, clipped to the grid
+    pub fn fill(&mut self, row: usize, col: usize, h: usize, w: usize, value: T) {
+        let r_end = (row + h).min(self.row0 + self.rows);
+        let c_end = (col + w).min(self.col0 + self.cols);
+        for r in row.max(self.row0)..r_end {
+            let base = (r - self.row0) * self.cols;
+            self.data[base + col.max(self.col0) - self.col0..base + c_end - self.col0].fill(value.clone());
+        }
+    }
+
+    /// Copy the overlapping part of another grid
+    pub fn paste(&mut self, src: &Grid<T>) {
+        let r_end = (src.row0 + src.rows).min(self.row0 + self.rows);
+        let c_end = (src.col0 + src.cols).min(self.col0 + self.cols);
+        let (r_start, c_start) = (src.row0.max(self.row0), src.col0.max(self.col0));
+        if c_start >= c_end {
+            return;
+        }
+        for r in r_start..r_end {
+            let d = (r - self.row0) * self.cols + c_start - self.col0;
+            let s = (r - src.row0) * src.cols + c_start - src.col0;
+            self.data[d..d + c_end - c_start].clone_from_slice(&src.data[s..s + c_end - c_start]);
+        }
+    }
+}
+
+/// One plane of 16-bit samples addressed with frame coordinates
+pub(super) type PlaneBuf = Grid<u16>;
+
+/// Decoded mode info of one 4x4 luma unit
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct MiInfo {
+    pub mi_size: u8,
+    pub skip: bool,
+    pub skip_mode: bool,
+    pub is_inter: bool,
+    pub y_mode: u8,
+    pub uv_mode: u8,
+    /// Reference frames; -1 marks an unused second reference
+    pub ref_frame: [i8; 2],
+    pub mv: [Mv; 2],
+    pub segment_id: u8,
+    /// Transform size used for the context of neighbouring transform sizes
+    pub inter_tx_size: u8,
+    pub tx_type: u8,
+    pub palette_size: [u8; 2],
+    /// Index of the block's palette colors in the tile's palette list
+    pub palette: u32,
+    pub interp_filter: [u8; 2],
+    pub comp_group_idx: u8,
+    pub compound_idx: u8,
+    pub delta_lf: [i8; 4],
+}
+
+/// A decoded frame kept for reference and output
+#[derive(Debug)]
+pub(super) struct RefFrame {
+    pub header: Arc<FrameHeader>,
+    pub planes: Vec<PlaneBuf>,
+    pub bit_depth: u8,
+    pub ss_x: bool,
+    pub ss_y: bool,
+    pub mi_rows: usize,
+    pub mi_cols: usize,
+    /// Reference frame and motion vector of every 4x4 unit, for motion
+    /// field projection
+    pub mvs: Grid<(i8, Mv)>,
+    pub segment_ids: Grid<u8>,
+    /// Symbol probabilities at the end of the frame
+    pub cdfs: Arc<CdfContext>,
+}
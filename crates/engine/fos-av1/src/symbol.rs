@@ -0,0 +1,172 @@
+//! AV1 Symbol Decoder
+//!
+//! Multi-symbol arithmetic decoder with adaptive 15-bit CDFs.
+
+const EC_PROB_SHIFT: u32 = 6;
+const EC_MIN_PROB: u32 = 4;
+
+/// Arithmetic decoder over the data of one tile
+#[derive(Debug)]
+pub(super) struct SymbolDecoder<'a> {
+    data: &'a [u8],
+    bit_pos: usize,
+    value: u32,
+    range: u32,
+    max_bits: i64,
+    allow_update: bool,
+}
+
+impl<'a> SymbolDecoder<'a> {
+    pub fn new(data: &'a [u8], allow_update: bool) -> Self {
+        let mut d = Self { data, bit_pos: 0, value: 0, range: 1 << 15, max_bits: 8 * data.len() as i64 - 15, allow_update };
+        let num_bits = (data.len() * 8).min(15) as u32;
+        let buf = d.read_bits(num_bits) << (15 - num_bits);
+        d.value = ((1 << 15) - 1) ^ buf;
+        d
+    }
+
+    fn read_bits(&mut self, n: u32) -> u32 {
+        let mut v = 0;
+        for _ in 0..n {
+            let bit = self.data.get(self.bit_pos / 8).map_or(0, |b| (b >> (7 - self.bit_pos % 8)) & 1);
+            v = (v << 1) | bit as u32;
+            self.bit_pos += 1;
+        }
+        v
+    }
+
+    /// Decode one symbol of `cdf` (N cumulative values and a counter) and
+    /// adapt the distribution towards it
+    pub fn read_symbol(&mut self, cdf: &mut [u16]) -> usize {
+        let n = cdf.len() - 1;
+        let symbol = self.decode(cdf, n);
+        if self.allow_update {
+            let rate = 3 + (cdf[n] > 15) as u32 + (cdf[n] > 31) as u32 + (n as u32).ilog2().min(2);
+            let mut tmp = 0;
+            for (i, c) in cdf.iter_mut().enumerate().take(n - 1) {
+                if i == symbol {
+                    tmp = 1 << 15;
+                }
+                if tmp < *c as u32 {
+                    *c -= ((*c as u32 - tmp) >> rate) as u16;
+                } else {
+                    *c += ((tmp - *c as u32) >> rate) as u16;
+                }
+            }
+            cdf[n] += (cdf[n] < 32) as u16;
+        }
+        symbol
+    }
+
+    /// Decode one symbol of a derived CDF that is not adapted
+    pub fn read_symbol_no_update(&mut self, cdf: &[u16]) -> usize {
+        self.decode(cdf, cdf.len() - 1)
+    }
+
+    fn decode(&mut self, cdf: &[u16], n: usize) -> usize {
+        let mut cur = self.range;
+        let mut prev;
+        let mut symbol = 0;
+        loop {
+            prev = cur;
+            let f = (1 << 15) - cdf[symbol] as u32;
+            cur = (((self.range >> 8) * (f >> EC_PROB_SHIFT)) >> (7 - EC_PROB_SHIFT)) + EC_MIN_PROB * (n - symbol - 1) as u32;
+            if self.value >= cur {
+                break;
+            }
+            symbol += 1;
+        }
+        self.range = prev - cur;
+        self.value -= cur;
+        let bits = 15 - self.range.ilog2();
+        self.range <<= bits;
+        let num_bits = (bits as i64).min(self.max_bits.max(0)) as u32;
+        let new_data = self.read_bits(num_bits) << (bits - num_bits);
+        self.value = new_data ^ (((self.value + 1) << bits) - 1);
+        self.max_bits -= bits as i64;
+        symbol
+    }
+
+    /// Whether more bits were read than the tile has, past the padding a
+    /// conforming tile allows for
+    pub fn overrun(&self) -> bool {
+        self.max_bits < -14
+    }
+
+    /// Decode an equiprobable bit
+    pub fn read_bool(&mut self) -> bool {
+        self.decode(&[1 << 14, 1 << 15], 2) == 1
+    }
+
+    /// Decode an `n`-bit unsigned literal, most significant bit first
+    pub fn read_literal(&mut self, n: u32) -> u32 {
+        (0..n).fold(0, |v, _| (v << 1) | self.read_bool() as u32)
+    }
+
+    /// Exp-Golomb code used for large coefficient levels
+    pub fn read_golomb(&mut self) -> u32 {
+        let mut length = 0;
+        while !self.read_bool() {
+            length += 1;
+            if length > 20 {
+                return 0;
+            }
+        }
+        let x = (1u32 << length) | self.read_literal(length);
+        x - 1
+    }
+
+    /// Non-symmetric unsigned value in `0..n`
+    pub fn read_ns(&mut self, n: u32) -> u32 {
+        let w = n.ilog2() + 1;
+        let m = (1 << w) - n;
+        let v = self.read_literal(w - 1);
+        if v < m {
+            return v;
+        }
+        (v << 1) - m + self.read_bool() as u32
+    }
+
+    /// Subexponential code used for loop restoration coefficients
+    pub fn decode_signed_subexp_with_ref_bool(&mut self, low: i32, high: i32, k: u32, r: i32) -> i32 {
+        let x = self.decode_unsigned_subexp_with_ref_bool(high - low, k, r - low);
+        x + low
+    }
+
+    fn decode_unsigned_subexp_with_ref_bool(&mut self, mx: i32, k: u32, r: i32) -> i32 {
+        let v = self.decode_subexp_bool(mx, k);
+        if (r << 1) <= mx {
+            inverse_recenter(r, v)
+        } else {
+            mx - 1 - inverse_recenter(mx - 1 - r, v)
+        }
+    }
+
+    fn decode_subexp_bool(&mut self, num_syms: i32, k: u32) -> i32 {
+        let mut i = 0;
+        let mut mk = 0;
+        loop {
+            let b2 = if i > 0 { k + i - 1 } else { k };
+            let a = 1 << b2;
+            if num_syms <= mk + 3 * a {
+                return self.read_ns((num_syms - mk) as u32) as i32 + mk;
+            }
+            if !self.read_bool() {
+                return self.read_literal(b2) as i32 + mk;
+            }
+            i += 1;
+            mk += a;
+        }
+    }
+}
+
+/// Map a value coded relative to the reference `r` back to its range
+pub(super) fn inverse_recenter(r: i32, v: i32) -> i32 {
+    if v > 2 * r {
+        v
+    } else if v & 1 != 0 {
+        r - ((v + 1) >> 1)
+    } else {
+        r + (v >> 1)
+    }
+}
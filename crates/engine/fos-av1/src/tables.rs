@@ -0,0 +1,1189 @@
+//! AV1 Decoding Tables
+//!
+//! Block and transform geometry, scan orders, quantizer lookups and
+//! prediction filter kernels.
+
+#![allow(clippy::unreadable_literal)]
+
+pub(super) const BLOCK_4X4: usize = 0;
+#[allow(dead_code)]
+pub(super) const BLOCK_4X8: usize = 1;
+#[allow(dead_code)]
+pub(super) const BLOCK_8X4: usize = 2;
+pub(super) const BLOCK_8X8: usize = 3;
+#[allow(dead_code)]
+pub(super) const BLOCK_16X16: usize = 6;
+pub(super) const BLOCK_32X32: usize = 9;
+pub(super) const BLOCK_64X64: usize = 12;
+pub(super) const BLOCK_128X128: usize = 15;
+pub(super) const BLOCK_INVALID: usize = 22;
+
+/// Block width and height, as log2 of the size in samples
+pub(super) const BLOCK_WIDTH_LOG2: [u8; 22] = [2, 2, 3, 3, 3, 4, 4, 4, 5, 5, 5, 6, 6, 6, 7, 7, 2, 4, 3, 5, 4, 6];
+pub(super) const BLOCK_HEIGHT_LOG2: [u8; 22] = [2, 3, 2, 3, 4, 3, 4, 5, 4, 5, 6, 5, 6, 7, 6, 7, 4, 2, 5, 3, 6, 4];
+pub(super) const SIZE_GROUP: [u8; 22] = [0, 0, 0, 1, 1, 1, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 0, 0, 1, 1, 2, 2];
+pub(super) const MAX_TX_DEPTH: [u8; 22] = [0, 1, 1, 1, 2, 2, 2, 3, 3, 3, 4, 4, 4, 4, 4, 4, 2, 2, 3, 3, 4, 4];
+pub(super) const WEDGE_BITS: [u8; 22] = [0, 0, 0, 4, 4, 4, 4, 4, 4, 4, 0, 0, 0, 0, 0, 0, 0, 0, 4, 4, 0, 0];
+/// Largest transform fitting each block size
+pub(super) const MAX_TX_SIZE_RECT: [usize; 22] = [
+    TX_4X4, TX_4X8, TX_8X4, TX_8X8, TX_8X16, TX_16X8, TX_16X16, TX_16X32, TX_32X16, TX_32X32, TX_32X64, TX_64X32,
+    TX_64X64, TX_64X64, TX_64X64, TX_64X64, TX_4X16, TX_16X4, TX_8X32, TX_32X8, TX_16X64, TX_64X16,
+];
+
+/// Block size with the given dimensions (log2 samples)
+pub(super) fn block_size(w_log2: u8, h_log2: u8) -> usize {
+    (0..22).find(|&b| BLOCK_WIDTH_LOG2[b] == w_log2 && BLOCK_HEIGHT_LOG2[b] == h_log2).unwrap_or(BLOCK_INVALID)
+}
+
+pub(super) fn block_width(bsize: usize) -> usize {
+    1 << BLOCK_WIDTH_LOG2[bsize]
+}
+
+pub(super) fn block_height(bsize: usize) -> usize {
+    1 << BLOCK_HEIGHT_LOG2[bsize]
+}
+
+pub(super) fn block_width4(bsize: usize) -> usize {
+    1 << (BLOCK_WIDTH_LOG2[bsize] - 2)
+}
+
+pub(super) fn block_height4(bsize: usize) -> usize {
+    1 << (BLOCK_HEIGHT_LOG2[bsize] - 2)
+}
+
+pub(super) const PARTITION_NONE: usize = 0;
+pub(super) const PARTITION_HORZ: usize = 1;
+pub(super) const PARTITION_VERT: usize = 2;
+pub(super) const PARTITION_SPLIT: usize = 3;
+pub(super) const PARTITION_HORZ_A: usize = 4;
+pub(super) const PARTITION_HORZ_B: usize = 5;
+pub(super) const PARTITION_VERT_A: usize = 6;
+pub(super) const PARTITION_VERT_B: usize = 7;
+pub(super) const PARTITION_HORZ_4: usize = 8;
+pub(super) const PARTITION_VERT_4: usize = 9;
+
+/// Size of the sub-blocks of a square block split with `partition`
+pub(super) fn partition_subsize(bsize: usize, partition: usize) -> usize {
+    let (w, h) = (BLOCK_WIDTH_LOG2[bsize], BLOCK_HEIGHT_LOG2[bsize]);
+    match partition {
+        PARTITION_NONE => bsize,
+        PARTITION_HORZ | PARTITION_HORZ_A | PARTITION_HORZ_B => block_size(w, h - 1),
+        PARTITION_VERT | PARTITION_VERT_A | PARTITION_VERT_B => block_size(w - 1, h),
+        PARTITION_SPLIT => block_size(w - 1, h - 1),
+        PARTITION_HORZ_4 => block_size(w, h - 2),
+        _ => block_size(w - 2, h),
+    }
+}
+
+/// Size of a chroma block covering the luma block `bsize`
+pub(super) fn subsampled_size(bsize: usize, ss_x: bool, ss_y: bool) -> usize {
+    let w = BLOCK_WIDTH_LOG2[bsize].saturating_sub(ss_x as u8).max(2);
+    let h = BLOCK_HEIGHT_LOG2[bsize].saturating_sub(ss_y as u8).max(2);
+    block_size(w, h)
+}
+
+pub(super) const TX_4X4: usize = 0;
+pub(super) const TX_8X8: usize = 1;
+pub(super) const TX_16X16: usize = 2;
+pub(super) const TX_32X32: usize = 3;
+pub(super) const TX_64X64: usize = 4;
+pub(super) const TX_4X8: usize = 5;
+pub(super) const TX_8X4: usize = 6;
+pub(super) const TX_8X16: usize = 7;
+pub(super) const TX_16X8: usize = 8;
+pub(super) const TX_16X32: usize = 9;
+pub(super) const TX_32X16: usize = 10;
+pub(super) const TX_32X64: usize = 11;
+pub(super) const TX_64X32: usize = 12;
+pub(super) const TX_4X16: usize = 13;
+pub(super) const TX_16X4: usize = 14;
+pub(super) const TX_8X32: usize = 15;
+pub(super) const TX_32X8: usize = 16;
+pub(super) const TX_16X64: usize = 17;
+pub(super) const TX_64X16: usize = 18;
+
+pub(super) const TX_WIDTH_LOG2: [u8; 19] = [2, 3, 4, 5, 6, 2, 3, 3, 4, 4, 5, 5, 6, 2, 4, 3, 5, 4, 6];
+pub(super) const TX_HEIGHT_LOG2: [u8; 19] = [2, 3, 4, 5, 6, 3, 2, 4, 3, 5, 4, 6, 5, 4, 2, 5, 3, 6, 4];
+pub(super) const TX_SIZE_SQR: [usize; 19] = [0, 1, 2, 3, 4, 0, 0, 1, 1, 2, 2, 3, 3, 0, 0, 1, 1, 2, 2];
+pub(super) const TX_SIZE_SQR_UP: [usize; 19] = [0, 1, 2, 3, 4, 1, 1, 2, 2, 3, 3, 4, 4, 2, 2, 3, 3, 4, 4];
+/// Next smaller transform in a split
+pub(super) const SPLIT_TX_SIZE: [usize; 19] = [
+    TX_4X4, TX_4X4, TX_8X8, TX_16X16, TX_32X32, TX_4X4, TX_4X4, TX_8X8, TX_8X8, TX_16X16, TX_16X16, TX_32X32,
+    TX_32X32, TX_4X8, TX_8X4, TX_8X16, TX_16X8, TX_16X32, TX_32X16,
+];
+
+pub(super) fn tx_width(tx: usize) -> usize {
+    1 << TX_WIDTH_LOG2[tx]
+}
+
+pub(super) fn tx_height(tx: usize) -> usize {
+    1 << TX_HEIGHT_LOG2[tx]
+}
+
+/// Transform size with the given dimensions (log2 samples)
+pub(super) fn tx_size(w_log2: u8, h_log2: u8) -> usize {
+    (0..19).find(|&t| TX_WIDTH_LOG2[t] == w_log2 && TX_HEIGHT_LOG2[t] == h_log2).unwrap_or(TX_4X4)
+}
+
+pub(super) const DCT_DCT: u8 = 0;
+pub(super) const ADST_DCT: u8 = 1;
+pub(super) const DCT_ADST: u8 = 2;
+pub(super) const ADST_ADST: u8 = 3;
+pub(super) const FLIPADST_DCT: u8 = 4;
+pub(super) const DCT_FLIPADST: u8 = 5;
+pub(super) const FLIPADST_FLIPADST: u8 = 6;
+pub(super) const ADST_FLIPADST: u8 = 7;
+pub(super) const FLIPADST_ADST: u8 = 8;
+pub(super) const IDTX: u8 = 9;
+pub(super) const V_DCT: u8 = 10;
+pub(super) const H_DCT: u8 = 11;
+pub(super) const V_ADST: u8 = 12;
+pub(super) const H_ADST: u8 = 13;
+pub(super) const V_FLIPADST: u8 = 14;
+pub(super) const H_FLIPADST: u8 = 15;
+
+pub(super) const TX_CLASS_2D: u8 = 0;
+pub(super) const TX_CLASS_HORIZ: u8 = 1;
+pub(super) const TX_CLASS_VERT: u8 = 2;
+
+pub(super) fn tx_class(tx_type: u8) -> u8 {
+    match tx_type {
+        V_DCT | V_ADST | V_FLIPADST => TX_CLASS_VERT,
+        H_DCT | H_ADST | H_FLIPADST => TX_CLASS_HORIZ,
+        _ => TX_CLASS_2D,
+    }
+}
+
+pub(super) const TX_SET_DCTONLY: usize = 0;
+pub(super) const TX_SET_INTRA_1: usize = 1;
+pub(super) const TX_SET_INTRA_2: usize = 2;
+pub(super) const TX_SET_INTER_1: usize = 1;
+pub(super) const TX_SET_INTER_2: usize = 2;
+pub(super) const TX_SET_INTER_3: usize = 3;
+
+/// Default transform of intra blocks, by prediction mode
+pub(super) const MODE_TO_TXFM: [u8; 14] = [
+    DCT_DCT, ADST_DCT, DCT_ADST, DCT_DCT, ADST_ADST, ADST_DCT, DCT_ADST, DCT_ADST, ADST_DCT, ADST_ADST, ADST_DCT,
+    DCT_ADST, ADST_ADST, DCT_DCT,
+];
+
+/// Row transform output shift, by transform size
+pub(super) const TRANSFORM_ROW_SHIFT: [u8; 19] = [0, 1, 2, 2, 2, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2];
+
+pub(super) const DC_PRED: u8 = 0;
+pub(super) const V_PRED: u8 = 1;
+pub(super) const H_PRED: u8 = 2;
+#[allow(dead_code)]
+pub(super) const D45_PRED: u8 = 3;
+#[allow(dead_code)]
+pub(super) const D135_PRED: u8 = 4;
+#[allow(dead_code)]
+pub(super) const D113_PRED: u8 = 5;
+pub(super) const D157_PRED: u8 = 6;
+#[allow(dead_code)]
+pub(super) const D203_PRED: u8 = 7;
+pub(super) const D67_PRED: u8 = 8;
+pub(super) const SMOOTH_PRED: u8 = 9;
+pub(super) const SMOOTH_V_PRED: u8 = 10;
+pub(super) const SMOOTH_H_PRED: u8 = 11;
+#[allow(dead_code)]
+pub(super) const PAETH_PRED: u8 = 12;
+pub(super) const UV_CFL_PRED: u8 = 13;
+pub(super) const NEARESTMV: u8 = 13;
+pub(super) const NEARMV: u8 = 14;
+pub(super) const GLOBALMV: u8 = 15;
+pub(super) const NEWMV: u8 = 16;
+pub(super) const NEAREST_NEARESTMV: u8 = 17;
+pub(super) const NEAR_NEARMV: u8 = 18;
+pub(super) const NEAREST_NEWMV: u8 = 19;
+pub(super) const NEW_NEARESTMV: u8 = 20;
+pub(super) const NEAR_NEWMV: u8 = 21;
+pub(super) const NEW_NEARMV: u8 = 22;
+pub(super) const GLOBAL_GLOBALMV: u8 = 23;
+pub(super) const NEW_NEWMV: u8 = 24;
+
+pub(super) const INTRA_MODE_CONTEXT: [usize; 13] = [0, 1, 2, 3, 4, 4, 4, 4, 3, 0, 1, 2, 0];
+pub(super) const MODE_TO_ANGLE: [i32; 9] = [0, 90, 180, 45, 135, 113, 157, 203, 67];
+pub(super) const COMPOUND_MODE_CTX_MAP: [[usize; 5]; 3] = [[0, 1, 1, 1, 1], [1, 2, 3, 4, 4], [4, 4, 5, 6, 7]];
+pub(super) const PALETTE_COLOR_CONTEXT: [i8; 9] = [-1, -1, 0, -1, -1, 4, 3, 2, 1];
+pub(super) const PALETTE_COLOR_HASH_MULTIPLIERS: [u32; 3] = [1, 2, 2];
+
+/// Dequantizer step, by bit depth and quantizer index
+pub(super) fn dc_q(bit_depth: u8, q: i32) -> i32 {
+    let q = q.clamp(0, 255) as usize;
+    (match bit_depth {
+        8 => DC_QLOOKUP[q],
+        10 => DC_QLOOKUP_10[q],
+        _ => DC_QLOOKUP_12[q],
+    }) as i32
+}
+
+pub(super) fn ac_q(bit_depth: u8, q: i32) -> i32 {
+    let q = q.clamp(0, 255) as usize;
+    (match bit_depth {
+        8 => AC_QLOOKUP[q],
+        10 => AC_QLOOKUP_10[q],
+        _ => AC_QLOOKUP_12[q],
+    }) as i32
+}
+
+/// Scan order of a transform: row, column or zig-zag
+pub(super) fn scan(tx: usize, tx_type: u8) -> std::borrow::Cow<'static, [u16]> {
+    let tx = match tx {
+        TX_16X64 => TX_16X32,
+        TX_64X16 => TX_32X16,
+        t if TX_SIZE_SQR_UP[t] == TX_64X64 => TX_32X32,
+        t => t,
+    };
+    let (w, h) = (tx_width(tx), tx_height(tx));
+    match tx_class(tx_type) {
+        // 1D transforms code whole rows (vertical) or columns first
+        TX_CLASS_VERT => (0..(w * h) as u16).collect(),
+        TX_CLASS_HORIZ => (0..w * h).map(|i| ((i % h) * w + i / h) as u16).collect(),
+        _ => std::borrow::Cow::Borrowed(default_scan(tx)),
+    }
+}
+
+fn default_scan(tx: usize) -> &'static [u16] {
+    match tx {
+        TX_4X4 => &DEFAULT_SCAN_4X4,
+        TX_8X8 => &DEFAULT_SCAN_8X8,
+        TX_16X16 => &DEFAULT_SCAN_16X16,
+        TX_32X32 => &DEFAULT_SCAN_32X32,
+        TX_4X8 => &DEFAULT_SCAN_4X8,
+        TX_8X4 => &DEFAULT_SCAN_8X4,
+        TX_8X16 => &DEFAULT_SCAN_8X16,
+        TX_16X8 => &DEFAULT_SCAN_16X8,
+        TX_16X32 => &DEFAULT_SCAN_16X32,
+        TX_32X16 => &DEFAULT_SCAN_32X16,
+        TX_4X16 => &DEFAULT_SCAN_4X16,
+        TX_16X4 => &DEFAULT_SCAN_16X4,
+        TX_8X32 => &DEFAULT_SCAN_8X32,
+        _ => &DEFAULT_SCAN_32X8,
+    }
+}
+
+/// Intra edge smoothing kernels, by strength
+pub(super) const INTRA_EDGE_KERNEL: [[i32; 5]; 3] = [[0, 4, 8, 4, 0], [0, 5, 6, 5, 0], [2, 4, 4, 4, 2]];
+
+/// Directional prediction step (in 1/64 samples), by angle
+pub(super) fn dr_intra_derivative(angle: i32) -> i32 {
+    match angle {
+        3 => 1023, 6 => 547, 9 => 372, 14 => 273, 17 => 215, 20 => 178, 23 => 151, 26 => 132, 29 => 116,
+        32 => 102, 36 => 90, 39 => 80, 42 => 71, 45 => 64, 48 => 57, 51 => 51, 54 => 45, 58 => 40, 61 => 35,
+        64 => 31, 67 => 27, 70 => 23, 73 => 19, 76 => 15, 81 => 11, 84 => 7, 87 => 3,
+        _ => 0,
+    }
+}
+
+/// Weights of the smooth predictors for a block dimension of `n` samples
+pub(super) fn sm_weights(n: usize) -> &'static [u8] {
+    &SM_WEIGHTS[n..2 * n]
+}
+
+/// Overlapped block motion compensation blend weights for `n` samples
+pub(super) fn obmc_mask(n: usize) -> &'static [u8] {
+    &OBMC_MASKS[n..2 * n]
+}
+
+/// Inter-intra smooth blend weights along one dimension
+pub(super) const II_WEIGHTS_1D: [u8; 128] = [
+    60, 58, 56, 54, 52, 50, 48, 47, 45, 44, 42, 41, 39, 38, 37, 35, 34, 33, 32, 31, 30, 29, 28, 27, 26, 25, 24, 23,
+    22, 22, 21, 20, 19, 19, 18, 18, 17, 16, 16, 15, 15, 14, 14, 13, 13, 12, 12, 12, 11, 11, 10, 10, 10, 9, 9, 9, 8,
+    8, 8, 8, 7, 7, 7, 7, 6, 6, 6, 6, 6, 5, 5, 5, 5, 5, 4, 4, 4, 4, 4, 4, 4, 4, 3, 3, 3, 3, 3, 3, 3, 3, 3, 2, 2, 2, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+];
+
+pub(super) const WEDGE_HORIZONTAL: u8 = 0;
+pub(super) const WEDGE_VERTICAL: u8 = 1;
+pub(super) const WEDGE_OBLIQUE27: u8 = 2;
+pub(super) const WEDGE_OBLIQUE63: u8 = 3;
+pub(super) const WEDGE_OBLIQUE117: u8 = 4;
+pub(super) const WEDGE_OBLIQUE153: u8 = 5;
+
+/// Wedge direction and offset (in eighths of the block), for blocks taller
+/// than wide, wider than tall, and square
+pub(super) const WEDGE_CODEBOOK: [[[u8; 3]; 16]; 3] = [
+    [
+        [WEDGE_OBLIQUE27, 4, 4], [WEDGE_OBLIQUE63, 4, 4], [WEDGE_OBLIQUE117, 4, 4], [WEDGE_OBLIQUE153, 4, 4],
+        [WEDGE_HORIZONTAL, 4, 2], [WEDGE_HORIZONTAL, 4, 4], [WEDGE_HORIZONTAL, 4, 6], [WEDGE_VERTICAL, 4, 4],
+        [WEDGE_OBLIQUE27, 4, 2], [WEDGE_OBLIQUE27, 4, 6], [WEDGE_OBLIQUE153, 4, 2], [WEDGE_OBLIQUE153, 4, 6],
+        [WEDGE_OBLIQUE63, 2, 4], [WEDGE_OBLIQUE63, 6, 4], [WEDGE_OBLIQUE117, 2, 4], [WEDGE_OBLIQUE117, 6, 4],
+    ],
+    [
+        [WEDGE_OBLIQUE27, 4, 4], [WEDGE_OBLIQUE63, 4, 4], [WEDGE_OBLIQUE117, 4, 4], [WEDGE_OBLIQUE153, 4, 4],
+        [WEDGE_VERTICAL, 2, 4], [WEDGE_VERTICAL, 4, 4], [WEDGE_VERTICAL, 6, 4], [WEDGE_HORIZONTAL, 4, 4],
+        [WEDGE_OBLIQUE27, 4, 2], [WEDGE_OBLIQUE27, 4, 6], [WEDGE_OBLIQUE153, 4, 2], [WEDGE_OBLIQUE153, 4, 6],
+        [WEDGE_OBLIQUE63, 2, 4], [WEDGE_OBLIQUE63, 6, 4], [WEDGE_OBLIQUE117, 2, 4], [WEDGE_OBLIQUE117, 6, 4],
+    ],
+    [
+        [WEDGE_OBLIQUE27, 4, 4], [WEDGE_OBLIQUE63, 4, 4], [WEDGE_OBLIQUE117, 4, 4], [WEDGE_OBLIQUE153, 4, 4],
+        [WEDGE_HORIZONTAL, 4, 2], [WEDGE_HORIZONTAL, 4, 6], [WEDGE_VERTICAL, 2, 4], [WEDGE_VERTICAL, 6, 4],
+        [WEDGE_OBLIQUE27, 4, 2], [WEDGE_OBLIQUE27, 4, 6], [WEDGE_OBLIQUE153, 4, 2], [WEDGE_OBLIQUE153, 4, 6],
+        [WEDGE_OBLIQUE63, 2, 4], [WEDGE_OBLIQUE63, 6, 4], [WEDGE_OBLIQUE117, 2, 4], [WEDGE_OBLIQUE117, 6, 4],
+    ],
+];
+
+/// Wedge transition profiles the masks are built from
+pub(super) const WEDGE_MASTER_OBLIQUE_ODD: [u8; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 6, 18, 37, 53,
+    60, 63, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64,
+];
+pub(super) const WEDGE_MASTER_OBLIQUE_EVEN: [u8; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 4, 11, 27, 46, 58,
+    62, 63, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64,
+];
+pub(super) const WEDGE_MASTER_VERTICAL: [u8; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 7, 21, 43, 57, 62, 64,
+    64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64,
+];
+
+/// Quantizer scaled distance weights for compound prediction
+pub(super) const QUANT_DIST_WEIGHT: [[i32; 2]; 4] = [[2, 3], [2, 5], [2, 7], [1, 16]];
+pub(super) const QUANT_DIST_LOOKUP: [[i32; 2]; 4] = [[9, 7], [11, 5], [12, 4], [13, 3]];
+
+/// Warp division lookup: 2^14 * 256 / (256 + i), rounded
+pub(super) fn div_lut(i: usize) -> i32 {
+    (((1u32 << 22) + (256 + i as u32) / 2) / (256 + i as u32)) as i32
+}
+
+/// CDEF filter taps: direction offsets (row, col) and strengths
+pub(super) const CDEF_DIRECTIONS: [[[i32; 2]; 2]; 8] = [
+    [[-1, 1], [-2, 2]], [[0, 1], [-1, 2]], [[0, 1], [0, 2]], [[0, 1], [1, 2]],
+    [[1, 1], [2, 2]], [[1, 0], [2, 1]], [[1, 0], [2, 0]], [[1, 0], [2, -1]],
+];
+pub(super) const CDEF_UV_DIR: [[[u8; 8]; 2]; 2] = [
+    [[0, 1, 2, 3, 4, 5, 6, 7], [1, 2, 2, 2, 3, 4, 6, 0]],
+    [[7, 0, 2, 4, 5, 6, 6, 6], [0, 1, 2, 3, 4, 5, 6, 7]],
+];
+pub(super) const CDEF_DIV_TABLE: [i32; 9] = [0, 840, 420, 280, 210, 168, 140, 120, 105];
+
+/// Self-guided filter radius and strength pairs
+pub(super) const SGR_PARAMS: [[i32; 4]; 16] = [
+    [2, 140, 1, 3236], [2, 112, 1, 2158], [2, 93, 1, 1618], [2, 80, 1, 1438], [2, 70, 1, 1295], [2, 58, 1, 1177],
+    [2, 47, 1, 1079], [2, 37, 1, 996], [2, 30, 1, 925], [2, 25, 1, 863], [0, -1, 1, 2589], [0, -1, 1, 1618],
+    [0, -1, 1, 1177], [0, -1, 1, 925], [2, 56, 0, -1], [2, 22, 0, -1],
+];
+pub(super) const WIENER_TAPS_MIN: [i32; 3] = [-5, -23, -17];
+pub(super) const WIENER_TAPS_MID: [i32; 3] = [3, -7, 15];
+pub(super) const WIENER_TAPS_MAX: [i32; 3] = [10, 8, 46];
+pub(super) const WIENER_TAPS_K: [u32; 3] = [1, 2, 3];
+pub(super) const SGRPROJ_XQD_MIN: [i32; 2] = [-96, -32];
+pub(super) const SGRPROJ_XQD_MID: [i32; 2] = [-32, 31];
+pub(super) const SGRPROJ_XQD_MAX: [i32; 2] = [31, 95];
+
+/// Super-resolution upscaling filter taps per 1/64 sample position
+pub(super) const UPSCALE_FILTER: [[i32; 8]; 64] = [
+    [0, 0, 0, 128, 0, 0, 0, 0], [0, 0, -1, 128, 2, -1, 0, 0],
+    [0, 1, -3, 127, 4, -2, 1, 0], [0, 1, -4, 127, 6, -3, 1, 0],
+    [0, 2, -6, 126, 8, -3, 1, 0], [0, 2, -7, 125, 11, -4, 1, 0],
+    [-1, 2, -8, 125, 13, -5, 2, 0], [-1, 3, -9, 124, 15, -6, 2, 0],
+    [-1, 3, -10, 123, 18, -6, 2, -1], [-1, 3, -11, 122, 20, -7, 3, -1],
+    [-1, 4, -12, 121, 22, -8, 3, -1], [-1, 4, -13, 120, 25, -9, 3, -1],
+    [-1, 4, -14, 118, 28, -9, 3, -1], [-1, 4, -15, 117, 30, -10, 4, -1],
+    [-1, 5, -16, 116, 32, -11, 4, -1], [-1, 5, -16, 114, 35, -12, 4, -1],
+    [-1, 5, -17, 112, 38, -12, 4, -1], [-1, 5, -18, 111, 40, -13, 5, -1],
+    [-1, 5, -18, 109, 43, -14, 5, -1], [-1, 6, -19, 107, 45, -14, 5, -1],
+    [-1, 6, -19, 105, 48, -15, 5, -1], [-1, 6, -19, 103, 51, -16, 5, -1],
+    [-1, 6, -20, 101, 53, -16, 6, -1], [-1, 6, -20, 99, 56, -17, 6, -1],
+    [-1, 6, -20, 97, 58, -17, 6, -1], [-1, 6, -20, 95, 61, -18, 6, -1],
+    [-2, 7, -20, 93, 64, -18, 6, -2], [-2, 7, -20, 91, 66, -19, 6, -1],
+    [-2, 7, -20, 88, 69, -19, 6, -1], [-2, 7, -20, 86, 71, -19, 6, -1],
+    [-2, 7, -20, 84, 74, -20, 7, -2], [-2, 7, -20, 81, 76, -20, 7, -1],
+    [-2, 7, -20, 79, 79, -20, 7, -2], [-1, 7, -20, 76, 81, -20, 7, -2],
+    [-2, 7, -20, 74, 84, -20, 7, -2], [-1, 6, -19, 71, 86, -20, 7, -2],
+    [-1, 6, -19, 69, 88, -20, 7, -2], [-1, 6, -19, 66, 91, -20, 7, -2],
+    [-2, 6, -18, 64, 93, -20, 7, -2], [-1, 6, -18, 61, 95, -20, 6, -1],
+    [-1, 6, -17, 58, 97, -20, 6, -1], [-1, 6, -17, 56, 99, -20, 6, -1],
+    [-1, 6, -16, 53, 101, -20, 6, -1], [-1, 5, -16, 51, 103, -19, 6, -1],
+    [-1, 5, -15, 48, 105, -19, 6, -1], [-1, 5, -14, 45, 107, -19, 6, -1],
+    [-1, 5, -14, 43, 109, -18, 5, -1], [-1, 5, -13, 40, 111, -18, 5, -1],
+    [-1, 4, -12, 38, 112, -17, 5, -1], [-1, 4, -12, 35, 114, -16, 5, -1],
+    [-1, 4, -11, 32, 116, -16, 5, -1], [-1, 4, -10, 30, 117, -15, 4, -1],
+    [-1, 3, -9, 28, 118, -14, 4, -1], [-1, 3, -9, 25, 120, -13, 4, -1],
+    [-1, 3, -8, 22, 121, -12, 4, -1], [-1, 3, -7, 20, 122, -11, 3, -1],
+    [-1, 2, -6, 18, 123, -10, 3, -1], [0, 2, -6, 15, 124, -9, 3, -1],
+    [0, 2, -5, 13, 125, -8, 2, -1], [0, 1, -4, 11, 125, -7, 2, 0],
+    [0, 1, -3, 8, 126, -6, 2, 0], [0, 1, -3, 6, 127, -4, 1, 0],
+    [0, 1, -2, 4, 127, -3, 1, 0], [0, 0, -1, 2, 128, -1, 0, 0],
+];
+
+/// Segmentation feature value widths, signedness and limits
+pub(super) const SEGMENTATION_FEATURE_BITS: [u32; 8] = [8, 6, 6, 6, 6, 3, 0, 0];
+pub(super) const SEGMENTATION_FEATURE_SIGNED: [bool; 8] = [true, true, true, true, true, false, false, false];
+pub(super) const SEGMENTATION_FEATURE_MAX: [i32; 8] = [255, 63, 63, 63, 63, 7, 0, 0];
+
+/// Transform types in the order they are coded, by transform set
+pub(super) const INTRA_SET1_TX_TYPES: [u8; 7] = [IDTX, DCT_DCT, V_DCT, H_DCT, ADST_ADST, ADST_DCT, DCT_ADST];
+pub(super) const INTRA_SET2_TX_TYPES: [u8; 5] = [IDTX, DCT_DCT, ADST_ADST, ADST_DCT, DCT_ADST];
+pub(super) const INTER_SET1_TX_TYPES: [u8; 16] = [IDTX, V_DCT, H_DCT, V_ADST, H_ADST, V_FLIPADST, H_FLIPADST, DCT_DCT, ADST_DCT, DCT_ADST, FLIPADST_DCT, DCT_FLIPADST, ADST_ADST, FLIPADST_FLIPADST, ADST_FLIPADST, FLIPADST_ADST];
+pub(super) const INTER_SET2_TX_TYPES: [u8; 12] = [IDTX, V_DCT, H_DCT, DCT_DCT, ADST_DCT, DCT_ADST, FLIPADST_DCT, DCT_FLIPADST, ADST_ADST, FLIPADST_FLIPADST, ADST_FLIPADST, FLIPADST_ADST];
+pub(super) const INTER_SET3_TX_TYPES: [u8; 2] = [IDTX, DCT_DCT];
+
+/// Coefficient base context offset of 2D transforms, by size, row and column
+pub(super) const COEFF_BASE_CTX_OFFSET: [[[u8; 5]; 5]; 19] = [
+    [[0, 1, 6, 6, 0], [1, 6, 6, 21, 0], [6, 6, 21, 21, 0], [6, 21, 21, 21, 0], [0, 0, 0, 0, 0]],
+    [[0, 1, 6, 6, 21], [1, 6, 6, 21, 21], [6, 6, 21, 21, 21], [6, 21, 21, 21, 21], [21, 21, 21, 21, 21]],
+    [[0, 1, 6, 6, 21], [1, 6, 6, 21, 21], [6, 6, 21, 21, 21], [6, 21, 21, 21, 21], [21, 21, 21, 21, 21]],
+    [[0, 1, 6, 6, 21], [1, 6, 6, 21, 21], [6, 6, 21, 21, 21], [6, 21, 21, 21, 21], [21, 21, 21, 21, 21]],
+    [[0, 1, 6, 6, 21], [1, 6, 6, 21, 21], [6, 6, 21, 21, 21], [6, 21, 21, 21, 21], [21, 21, 21, 21, 21]],
+    [[0, 11, 11, 11, 0], [11, 11, 11, 11, 0], [6, 6, 21, 21, 0], [6, 21, 21, 21, 0], [21, 21, 21, 21, 0]],
+    [[0, 16, 6, 6, 21], [16, 16, 6, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21], [0, 0, 0, 0, 0]],
+    [[0, 11, 11, 11, 11], [11, 11, 11, 11, 11], [6, 6, 21, 21, 21], [6, 21, 21, 21, 21], [21, 21, 21, 21, 21]],
+    [[0, 16, 6, 6, 21], [16, 16, 6, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21]],
+    [[0, 11, 11, 11, 11], [11, 11, 11, 11, 11], [6, 6, 21, 21, 21], [6, 21, 21, 21, 21], [21, 21, 21, 21, 21]],
+    [[0, 16, 6, 6, 21], [16, 16, 6, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21]],
+    [[0, 11, 11, 11, 11], [11, 11, 11, 11, 11], [6, 6, 21, 21, 21], [6, 21, 21, 21, 21], [21, 21, 21, 21, 21]],
+    [[0, 16, 6, 6, 21], [16, 16, 6, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21]],
+    [[0, 11, 11, 11, 0], [11, 11, 11, 11, 0], [6, 6, 21, 21, 0], [6, 21, 21, 21, 0], [21, 21, 21, 21, 0]],
+    [[0, 16, 6, 6, 21], [16, 16, 6, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21], [0, 0, 0, 0, 0]],
+    [[0, 11, 11, 11, 11], [11, 11, 11, 11, 11], [6, 6, 21, 21, 21], [6, 21, 21, 21, 21], [21, 21, 21, 21, 21]],
+    [[0, 16, 6, 6, 21], [16, 16, 6, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21]],
+    [[0, 11, 11, 11, 11], [11, 11, 11, 11, 11], [6, 6, 21, 21, 21], [6, 21, 21, 21, 21], [21, 21, 21, 21, 21]],
+    [[0, 16, 6, 6, 21], [16, 16, 6, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21], [16, 16, 21, 21, 21]],
+];
+
+const DEFAULT_SCAN_4X4: [u16; 16] = [
+    0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15,
+];
+
+const DEFAULT_SCAN_8X4: [u16; 32] = [
+    0, 8, 1, 16, 9, 2, 24, 17, 10, 3, 25, 18, 11, 4, 26, 19,
+    12, 5, 27, 20, 13, 6, 28, 21, 14, 7, 29, 22, 15, 30, 23, 31,
+];
+
+const DEFAULT_SCAN_4X8: [u16; 32] = [
+    0, 1, 4, 2, 5, 8, 3, 6, 9, 12, 7, 10, 13, 16, 11, 14,
+    17, 20, 15, 18, 21, 24, 19, 22, 25, 28, 23, 26, 29, 27, 30, 31,
+];
+
+const DEFAULT_SCAN_16X4: [u16; 64] = [
+    0, 16, 1, 32, 17, 2, 48, 33, 18, 3, 49, 34, 19, 4, 50, 35,
+    20, 5, 51, 36, 21, 6, 52, 37, 22, 7, 53, 38, 23, 8, 54, 39,
+    24, 9, 55, 40, 25, 10, 56, 41, 26, 11, 57, 42, 27, 12, 58, 43,
+    28, 13, 59, 44, 29, 14, 60, 45, 30, 15, 61, 46, 31, 62, 47, 63,
+];
+
+const DEFAULT_SCAN_4X16: [u16; 64] = [
+    0, 1, 4, 2, 5, 8, 3, 6, 9, 12, 7, 10, 13, 16, 11, 14,
+    17, 20, 15, 18, 21, 24, 19, 22, 25, 28, 23, 26, 29, 32, 27, 30,
+    33, 36, 31, 34, 37, 40, 35, 38, 41, 44, 39, 42, 45, 48, 43, 46,
+    49, 52, 47, 50, 53, 56, 51, 54, 57, 60, 55, 58, 61, 59, 62, 63,
+];
+
+const DEFAULT_SCAN_8X8: [u16; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5,
+    12, 19, 26, 33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51,
+    58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+const DEFAULT_SCAN_16X8: [u16; 128] = [
+    0, 16, 1, 32, 17, 2, 48, 33, 18, 3, 64, 49, 34, 19, 4, 80,
+    65, 50, 35, 20, 5, 96, 81, 66, 51, 36, 21, 6, 112, 97, 82, 67,
+    52, 37, 22, 7, 113, 98, 83, 68, 53, 38, 23, 8, 114, 99, 84, 69,
+    54, 39, 24, 9, 115, 100, 85, 70, 55, 40, 25, 10, 116, 101, 86, 71,
+    56, 41, 26, 11, 117, 102, 87, 72, 57, 42, 27, 12, 118, 103, 88, 73,
+    58, 43, 28, 13, 119, 104, 89, 74, 59, 44, 29, 14, 120, 105, 90, 75,
+    60, 45, 30, 15, 121, 106, 91, 76, 61, 46, 31, 122, 107, 92, 77, 62,
+    47, 123, 108, 93, 78, 63, 124, 109, 94, 79, 125, 110, 95, 126, 111, 127,
+];
+
+const DEFAULT_SCAN_8X16: [u16; 128] = [
+    0, 1, 8, 2, 9, 16, 3, 10, 17, 24, 4, 11, 18, 25, 32, 5,
+    12, 19, 26, 33, 40, 6, 13, 20, 27, 34, 41, 48, 7, 14, 21, 28,
+    35, 42, 49, 56, 15, 22, 29, 36, 43, 50, 57, 64, 23, 30, 37, 44,
+    51, 58, 65, 72, 31, 38, 45, 52, 59, 66, 73, 80, 39, 46, 53, 60,
+    67, 74, 81, 88, 47, 54, 61, 68, 75, 82, 89, 96, 55, 62, 69, 76,
+    83, 90, 97, 104, 63, 70, 77, 84, 91, 98, 105, 112, 71, 78, 85, 92,
+    99, 106, 113, 120, 79, 86, 93, 100, 107, 114, 121, 87, 94, 101, 108, 115,
+    122, 95, 102, 109, 116, 123, 103, 110, 117, 124, 111, 118, 125, 119, 126, 127,
+];
+
+const DEFAULT_SCAN_16X16: [u16; 256] = [
+    0, 1, 16, 32, 17, 2, 3, 18, 33, 48, 64, 49, 34, 19, 4, 5,
+    20, 35, 50, 65, 80, 96, 81, 66, 51, 36, 21, 6, 7, 22, 37, 52,
+    67, 82, 97, 112, 128, 113, 98, 83, 68, 53, 38, 23, 8, 9, 24, 39,
+    54, 69, 84, 99, 114, 129, 144, 160, 145, 130, 115, 100, 85, 70, 55, 40,
+    25, 10, 11, 26, 41, 56, 71, 86, 101, 116, 131, 146, 161, 176, 192, 177,
+    162, 147, 132, 117, 102, 87, 72, 57, 42, 27, 12, 13, 28, 43, 58, 73,
+    88, 103, 118, 133, 148, 163, 178, 193, 208, 224, 209, 194, 179, 164, 149, 134,
+    119, 104, 89, 74, 59, 44, 29, 14, 15, 30, 45, 60, 75, 90, 105, 120,
+    135, 150, 165, 180, 195, 210, 225, 240, 241, 226, 211, 196, 181, 166, 151, 136,
+    121, 106, 91, 76, 61, 46, 31, 47, 62, 77, 92, 107, 122, 137, 152, 167,
+    182, 197, 212, 227, 242, 243, 228, 213, 198, 183, 168, 153, 138, 123, 108, 93,
+    78, 63, 79, 94, 109, 124, 139, 154, 169, 184, 199, 214, 229, 244, 245, 230,
+    215, 200, 185, 170, 155, 140, 125, 110, 95, 111, 126, 141, 156, 171, 186, 201,
+    216, 231, 246, 247, 232, 217, 202, 187, 172, 157, 142, 127, 143, 158, 173, 188,
+    203, 218, 233, 248, 249, 234, 219, 204, 189, 174, 159, 175, 190, 205, 220, 235,
+    250, 251, 236, 221, 206, 191, 207, 222, 237, 252, 253, 238, 223, 239, 254, 255,
+];
+
+const DEFAULT_SCAN_32X8: [u16; 256] = [
+    0, 32, 1, 64, 33, 2, 96, 65, 34, 3, 128, 97, 66, 35, 4, 160,
+    129, 98, 67, 36, 5, 192, 161, 130, 99, 68, 37, 6, 224, 193, 162, 131,
+    100, 69, 38, 7, 225, 194, 163, 132, 101, 70, 39, 8, 226, 195, 164, 133,
+    102, 71, 40, 9, 227, 196, 165, 134, 103, 72, 41, 10, 228, 197, 166, 135,
+    104, 73, 42, 11, 229, 198, 167, 136, 105, 74, 43, 12, 230, 199, 168, 137,
+    106, 75, 44, 13, 231, 200, 169, 138, 107, 76, 45, 14, 232, 201, 170, 139,
+    108, 77, 46, 15, 233, 202, 171, 140, 109, 78, 47, 16, 234, 203, 172, 141,
+    110, 79, 48, 17, 235, 204, 173, 142, 111, 80, 49, 18, 236, 205, 174, 143,
+    112, 81, 50, 19, 237, 206, 175, 144, 113, 82, 51, 20, 238, 207, 176, 145,
+    114, 83, 52, 21, 239, 208, 177, 146, 115, 84, 53, 22, 240, 209, 178, 147,
+    116, 85, 54, 23, 241, 210, 179, 148, 117, 86, 55, 24, 242, 211, 180, 149,
+    118, 87, 56, 25, 243, 212, 181, 150, 119, 88, 57, 26, 244, 213, 182, 151,
+    120, 89, 58, 27, 245, 214, 183, 152, 121, 90, 59, 28, 246, 215, 184, 153,
+    122, 91, 60, 29, 247, 216, 185, 154, 123, 92, 61, 30, 248, 217, 186, 155,
+    124, 93, 62, 31, 249, 218, 187, 156, 125, 94, 63, 250, 219, 188, 157, 126,
+    95, 251, 220, 189, 158, 127, 252, 221, 190, 159, 253, 222, 191, 254, 223, 255,
+];
+
+const DEFAULT_SCAN_8X32: [u16; 256] = [
+    0, 1, 8, 2, 9, 16, 3, 10, 17, 24, 4, 11, 18, 25, 32, 5,
+    12, 19, 26, 33, 40, 6, 13, 20, 27, 34, 41, 48, 7, 14, 21, 28,
+    35, 42, 49, 56, 15, 22, 29, 36, 43, 50, 57, 64, 23, 30, 37, 44,
+    51, 58, 65, 72, 31, 38, 45, 52, 59, 66, 73, 80, 39, 46, 53, 60,
+    67, 74, 81, 88, 47, 54, 61, 68, 75, 82, 89, 96, 55, 62, 69, 76,
+    83, 90, 97, 104, 63, 70, 77, 84, 91, 98, 105, 112, 71, 78, 85, 92,
+    99, 106, 113, 120, 79, 86, 93, 100, 107, 114, 121, 128, 87, 94, 101, 108,
+    115, 122, 129, 136, 95, 102, 109, 116, 123, 130, 137, 144, 103, 110, 117, 124,
+    131, 138, 145, 152, 111, 118, 125, 132, 139, 146, 153, 160, 119, 126, 133, 140,
+    147, 154, 161, 168, 127, 134, 141, 148, 155, 162, 169, 176, 135, 142, 149, 156,
+    163, 170, 177, 184, 143, 150, 157, 164, 171, 178, 185, 192, 151, 158, 165, 172,
+    179, 186, 193, 200, 159, 166, 173, 180, 187, 194, 201, 208, 167, 174, 181, 188,
+    195, 202, 209, 216, 175, 182, 189, 196, 203, 210, 217, 224, 183, 190, 197, 204,
+    211, 218, 225, 232, 191, 198, 205, 212, 219, 226, 233, 240, 199, 206, 213, 220,
+    227, 234, 241, 248, 207, 214, 221, 228, 235, 242, 249, 215, 222, 229, 236, 243,
+    250, 223, 230, 237, 244, 251, 231, 238, 245, 252, 239, 246, 253, 247, 254, 255,
+];
+
+const DEFAULT_SCAN_32X16: [u16; 512] = [
+    0, 32, 1, 64, 33, 2, 96, 65, 34, 3, 128, 97, 66, 35, 4, 160,
+    129, 98, 67, 36, 5, 192, 161, 130, 99, 68, 37, 6, 224, 193, 162, 131,
+    100, 69, 38, 7, 256, 225, 194, 163, 132, 101, 70, 39, 8, 288, 257, 226,
+    195, 164, 133, 102, 71, 40, 9, 320, 289, 258, 227, 196, 165, 134, 103, 72,
+    41, 10, 352, 321, 290, 259, 228, 197, 166, 135, 104, 73, 42, 11, 384, 353,
+    322, 291, 260, 229, 198, 167, 136, 105, 74, 43, 12, 416, 385, 354, 323, 292,
+    261, 230, 199, 168, 137, 106, 75, 44, 13, 448, 417, 386, 355, 324, 293, 262,
+    231, 200, 169, 138, 107, 76, 45, 14, 480, 449, 418, 387, 356, 325, 294, 263,
+    232, 201, 170, 139, 108, 77, 46, 15, 481, 450, 419, 388, 357, 326, 295, 264,
+    233, 202, 171, 140, 109, 78, 47, 16, 482, 451, 420, 389, 358, 327, 296, 265,
+    234, 203, 172, 141, 110, 79, 48, 17, 483, 452, 421, 390, 359, 328, 297, 266,
+    235, 204, 173, 142, 111, 80, 49, 18, 484, 453, 422, 391, 360, 329, 298, 267,
+    236, 205, 174, 143, 112, 81, 50, 19, 485, 454, 423, 392, 361, 330, 299, 268,
+    237, 206, 175, 144, 113, 82, 51, 20, 486, 455, 424, 393, 362, 331, 300, 269,
+    238, 207, 176, 145, 114, 83, 52, 21, 487, 456, 425, 394, 363, 332, 301, 270,
+    239, 208, 177, 146, 115, 84, 53, 22, 488, 457, 426, 395, 364, 333, 302, 271,
+    240, 209, 178, 147, 116, 85, 54, 23, 489, 458, 427, 396, 365, 334, 303, 272,
+    241, 210, 179, 148, 117, 86, 55, 24, 490, 459, 428, 397, 366, 335, 304, 273,
+    242, 211, 180, 149, 118, 87, 56, 25, 491, 460, 429, 398, 367, 336, 305, 274,
+    243, 212, 181, 150, 119, 88, 57, 26, 492, 461, 430, 399, 368, 337, 306, 275,
+    244, 213, 182, 151, 120, 89, 58, 27, 493, 462, 431, 400, 369, 338, 307, 276,
+    245, 214, 183, 152, 121, 90, 59, 28, 494, 463, 432, 401, 370, 339, 308, 277,
+    246, 215, 184, 153, 122, 91, 60, 29, 495, 464, 433, 402, 371, 340, 309, 278,
+    247, 216, 185, 154, 123, 92, 61, 30, 496, 465, 434, 403, 372, 341, 310, 279,
+    248, 217, 186, 155, 124, 93, 62, 31, 497, 466, 435, 404, 373, 342, 311, 280,
+    249, 218, 187, 156, 125, 94, 63, 498, 467, 436, 405, 374, 343, 312, 281, 250,
+    219, 188, 157, 126, 95, 499, 468, 437, 406, 375, 344, 313, 282, 251, 220, 189,
+    158, 127, 500, 469, 438, 407, 376, 345, 314, 283, 252, 221, 190, 159, 501, 470,
+    439, 408, 377, 346, 315, 284, 253, 222, 191, 502, 471, 440, 409, 378, 347, 316,
+    285, 254, 223, 503, 472, 441, 410, 379, 348, 317, 286, 255, 504, 473, 442, 411,
+    380, 349, 318, 287, 505, 474, 443, 412, 381, 350, 319, 506, 475, 444, 413, 382,
+    351, 507, 476, 445, 414, 383, 508, 477, 446, 415, 509, 478, 447, 510, 479, 511,
+];
+
+const DEFAULT_SCAN_16X32: [u16; 512] = [
+    0, 1, 16, 2, 17, 32, 3, 18, 33, 48, 4, 19, 34, 49, 64, 5,
+    20, 35, 50, 65, 80, 6, 21, 36, 51, 66, 81, 96, 7, 22, 37, 52,
+    67, 82, 97, 112, 8, 23, 38, 53, 68, 83, 98, 113, 128, 9, 24, 39,
+    54, 69, 84, 99, 114, 129, 144, 10, 25, 40, 55, 70, 85, 100, 115, 130,
+    145, 160, 11, 26, 41, 56, 71, 86, 101, 116, 131, 146, 161, 176, 12, 27,
+    42, 57, 72, 87, 102, 117, 132, 147, 162, 177, 192, 13, 28, 43, 58, 73,
+    88, 103, 118, 133, 148, 163, 178, 193, 208, 14, 29, 44, 59, 74, 89, 104,
+    119, 134, 149, 164, 179, 194, 209, 224, 15, 30, 45, 60, 75, 90, 105, 120,
+    135, 150, 165, 180, 195, 210, 225, 240, 31, 46, 61, 76, 91, 106, 121, 136,
+    151, 166, 181, 196, 211, 226, 241, 256, 47, 62, 77, 92, 107, 122, 137, 152,
+    167, 182, 197, 212, 227, 242, 257, 272, 63, 78, 93, 108, 123, 138, 153, 168,
+    183, 198, 213, 228, 243, 258, 273, 288, 79, 94, 109, 124, 139, 154, 169, 184,
+    199, 214, 229, 244, 259, 274, 289, 304, 95, 110, 125, 140, 155, 170, 185, 200,
+    215, 230, 245, 260, 275, 290, 305, 320, 111, 126, 141, 156, 171, 186, 201, 216,
+    231, 246, 261, 276, 291, 306, 321, 336, 127, 142, 157, 172, 187, 202, 217, 232,
+    247, 262, 277, 292, 307, 322, 337, 352, 143, 158, 173, 188, 203, 218, 233, 248,
+    263, 278, 293, 308, 323, 338, 353, 368, 159, 174, 189, 204, 219, 234, 249, 264,
+    279, 294, 309, 324, 339, 354, 369, 384, 175, 190, 205, 220, 235, 250, 265, 280,
+    295, 310, 325, 340, 355, 370, 385, 400, 191, 206, 221, 236, 251, 266, 281, 296,
+    311, 326, 341, 356, 371, 386, 401, 416, 207, 222, 237, 252, 267, 282, 297, 312,
+    327, 342, 357, 372, 387, 402, 417, 432, 223, 238, 253, 268, 283, 298, 313, 328,
+    343, 358, 373, 388, 403, 418, 433, 448, 239, 254, 269, 284, 299, 314, 329, 344,
+    359, 374, 389, 404, 419, 434, 449, 464, 255, 270, 285, 300, 315, 330, 345, 360,
+    375, 390, 405, 420, 435, 450, 465, 480, 271, 286, 301, 316, 331, 346, 361, 376,
+    391, 406, 421, 436, 451, 466, 481, 496, 287, 302, 317, 332, 347, 362, 377, 392,
+    407, 422, 437, 452, 467, 482, 497, 303, 318, 333, 348, 363, 378, 393, 408, 423,
+    438, 453, 468, 483, 498, 319, 334, 349, 364, 379, 394, 409, 424, 439, 454, 469,
+    484, 499, 335, 350, 365, 380, 395, 410, 425, 440, 455, 470, 485, 500, 351, 366,
+    381, 396, 411, 426, 441, 456, 471, 486, 501, 367, 382, 397, 412, 427, 442, 457,
+    472, 487, 502, 383, 398, 413, 428, 443, 458, 473, 488, 503, 399, 414, 429, 444,
+    459, 474, 489, 504, 415, 430, 445, 460, 475, 490, 505, 431, 446, 461, 476, 491,
+    506, 447, 462, 477, 492, 507, 463, 478, 493, 508, 479, 494, 509, 495, 510, 511,
+];
+
+const DEFAULT_SCAN_32X32: [u16; 1024] = [
+    0, 1, 32, 64, 33, 2, 3, 34, 65, 96, 128, 97, 66, 35, 4, 5,
+    36, 67, 98, 129, 160, 192, 161, 130, 99, 68, 37, 6, 7, 38, 69, 100,
+    131, 162, 193, 224, 256, 225, 194, 163, 132, 101, 70, 39, 8, 9, 40, 71,
+    102, 133, 164, 195, 226, 257, 288, 320, 289, 258, 227, 196, 165, 134, 103, 72,
+    41, 10, 11, 42, 73, 104, 135, 166, 197, 228, 259, 290, 321, 352, 384, 353,
+    322, 291, 260, 229, 198, 167, 136, 105, 74, 43, 12, 13, 44, 75, 106, 137,
+    168, 199, 230, 261, 292, 323, 354, 385, 416, 448, 417, 386, 355, 324, 293, 262,
+    231, 200, 169, 138, 107, 76, 45, 14, 15, 46, 77, 108, 139, 170, 201, 232,
+    263, 294, 325, 356, 387, 418, 449, 480, 512, 481, 450, 419, 388, 357, 326, 295,
+    264, 233, 202, 171, 140, 109, 78, 47, 16, 17, 48, 79, 110, 141, 172, 203,
+    234, 265, 296, 327, 358, 389, 420, 451, 482, 513, 544, 576, 545, 514, 483, 452,
+    421, 390, 359, 328, 297, 266, 235, 204, 173, 142, 111, 80, 49, 18, 19, 50,
+    81, 112, 143, 174, 205, 236, 267, 298, 329, 360, 391, 422, 453, 484, 515, 546,
+    577, 608, 640, 609, 578, 547, 516, 485, 454, 423, 392, 361, 330, 299, 268, 237,
+    206, 175, 144, 113, 82, 51, 20, 21, 52, 83, 114, 145, 176, 207, 238, 269,
+    300, 331, 362, 393, 424, 455, 486, 517, 548, 579, 610, 641, 672, 704, 673, 642,
+    611, 580, 549, 518, 487, 456, 425, 394, 363, 332, 301, 270, 239, 208, 177, 146,
+    115, 84, 53, 22, 23, 54, 85, 116, 147, 178, 209, 240, 271, 302, 333, 364,
+    395, 426, 457, 488, 519, 550, 581, 612, 643, 674, 705, 736, 768, 737, 706, 675,
+    644, 613, 582, 551, 520, 489, 458, 427, 396, 365, 334, 303, 272, 241, 210, 179,
+    148, 117, 86, 55, 24, 25, 56, 87, 118, 149, 180, 211, 242, 273, 304, 335,
+    366, 397, 428, 459, 490, 521, 552, 583, 614, 645, 676, 707, 738, 769, 800, 832,
+    801, 770, 739, 708, 677, 646, 615, 584, 553, 522, 491, 460, 429, 398, 367, 336,
+    305, 274, 243, 212, 181, 150, 119, 88, 57, 26, 27, 58, 89, 120, 151, 182,
+    213, 244, 275, 306, 337, 368, 399, 430, 461, 492, 523, 554, 585, 616, 647, 678,
+    709, 740, 771, 802, 833, 864, 896, 865, 834, 803, 772, 741, 710, 679, 648, 617,
+    586, 555, 524, 493, 462, 431, 400, 369, 338, 307, 276, 245, 214, 183, 152, 121,
+    90, 59, 28, 29, 60, 91, 122, 153, 184, 215, 246, 277, 308, 339, 370, 401,
+    432, 463, 494, 525, 556, 587, 618, 649, 680, 711, 742, 773, 804, 835, 866, 897,
+    928, 960, 929, 898, 867, 836, 805, 774, 743, 712, 681, 650, 619, 588, 557, 526,
+    495, 464, 433, 402, 371, 340, 309, 278, 247, 216, 185, 154, 123, 92, 61, 30,
+    31, 62, 93, 124, 155, 186, 217, 248, 279, 310, 341, 372, 403, 434, 465, 496,
+    527, 558, 589, 620, 651, 682, 713, 744, 775, 806, 837, 868, 899, 930, 961, 992,
+    993, 962, 931, 900, 869, 838, 807, 776, 745, 714, 683, 652, 621, 590, 559, 528,
+    497, 466, 435, 404, 373, 342, 311, 280, 249, 218, 187, 156, 125, 94, 63, 95,
+    126, 157, 188, 219, 250, 281, 312, 343, 374, 405, 436, 467, 498, 529, 560, 591,
+    622, 653, 684, 715, 746, 777, 808, 839, 870, 901, 932, 963, 994, 995, 964, 933,
+    902, 871, 840, 809, 778, 747, 716, 685, 654, 623, 592, 561, 530, 499, 468, 437,
+    406, 375, 344, 313, 282, 251, 220, 189, 158, 127, 159, 190, 221, 252, 283, 314,
+    345, 376, 407, 438, 469, 500, 531, 562, 593, 624, 655, 686, 717, 748, 779, 810,
+    841, 872, 903, 934, 965, 996, 997, 966, 935, 904, 873, 842, 811, 780, 749, 718,
+    687, 656, 625, 594, 563, 532, 501, 470, 439, 408, 377, 346, 315, 284, 253, 222,
+    191, 223, 254, 285, 316, 347, 378, 409, 440, 471, 502, 533, 564, 595, 626, 657,
+    688, 719, 750, 781, 812, 843, 874, 905, 936, 967, 998, 999, 968, 937, 906, 875,
+    844, 813, 782, 751, 720, 689, 658, 627, 596, 565, 534, 503, 472, 441, 410, 379,
+    348, 317, 286, 255, 287, 318, 349, 380, 411, 442, 473, 504, 535, 566, 597, 628,
+    659, 690, 721, 752, 783, 814, 845, 876, 907, 938, 969, 1000, 1001, 970, 939, 908,
+    877, 846, 815, 784, 753, 722, 691, 660, 629, 598, 567, 536, 505, 474, 443, 412,
+    381, 350, 319, 351, 382, 413, 444, 475, 506, 537, 568, 599, 630, 661, 692, 723,
+    754, 785, 816, 847, 878, 909, 940, 971, 1002, 1003, 972, 941, 910, 879, 848, 817,
+    786, 755, 724, 693, 662, 631, 600, 569, 538, 507, 476, 445, 414, 383, 415, 446,
+    477, 508, 539, 570, 601, 632, 663, 694, 725, 756, 787, 818, 849, 880, 911, 942,
+    973, 1004, 1005, 974, 943, 912, 881, 850, 819, 788, 757, 726, 695, 664, 633, 602,
+    571, 540, 509, 478, 447, 479, 510, 541, 572, 603, 634, 665, 696, 727, 758, 789,
+    820, 851, 882, 913, 944, 975, 1006, 1007, 976, 945, 914, 883, 852, 821, 790, 759,
+    728, 697, 666, 635, 604, 573, 542, 511, 543, 574, 605, 636, 667, 698, 729, 760,
+    791, 822, 853, 884, 915, 946, 977, 1008, 1009, 978, 947, 916, 885, 854, 823, 792,
+    761, 730, 699, 668, 637, 606, 575, 607, 638, 669, 700, 731, 762, 793, 824, 855,
+    886, 917, 948, 979, 1010, 1011, 980, 949, 918, 887, 856, 825, 794, 763, 732, 701,
+    670, 639, 671, 702, 733, 764, 795, 826, 857, 888, 919, 950, 981, 1012, 1013, 982,
+    951, 920, 889, 858, 827, 796, 765, 734, 703, 735, 766, 797, 828, 859, 890, 921,
+    952, 983, 1014, 1015, 984, 953, 922, 891, 860, 829, 798, 767, 799, 830, 861, 892,
+    923, 954, 985, 1016, 1017, 986, 955, 924, 893, 862, 831, 863, 894, 925, 956, 987,
+    1018, 1019, 988, 957, 926, 895, 927, 958, 989, 1020, 1021, 990, 959, 991, 1022, 1023,
+];
+
+const DC_QLOOKUP: [u16; 256] = [
+    4, 8, 8, 9, 10, 11, 12, 12, 13, 14, 15, 16, 17, 18, 19, 19,
+    20, 21, 22, 23, 24, 25, 26, 26, 27, 28, 29, 30, 31, 32, 32, 33,
+    34, 35, 36, 37, 38, 38, 39, 40, 41, 42, 43, 43, 44, 45, 46, 47,
+    48, 48, 49, 50, 51, 52, 53, 53, 54, 55, 56, 57, 57, 58, 59, 60,
+    61, 62, 62, 63, 64, 65, 66, 66, 67, 68, 69, 70, 70, 71, 72, 73,
+    74, 74, 75, 76, 77, 78, 78, 79, 80, 81, 81, 82, 83, 84, 85, 85,
+    87, 88, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104, 105, 107, 108, 110,
+    111, 113, 114, 116, 117, 118, 120, 121, 123, 125, 127, 129, 131, 134, 136, 138,
+    140, 142, 144, 146, 148, 150, 152, 154, 156, 158, 161, 164, 166, 169, 172, 174,
+    177, 180, 182, 185, 187, 190, 192, 195, 199, 202, 205, 208, 211, 214, 217, 220,
+    223, 226, 230, 233, 237, 240, 243, 247, 250, 253, 257, 261, 265, 269, 272, 276,
+    280, 284, 288, 292, 296, 300, 304, 309, 313, 317, 322, 326, 330, 335, 340, 344,
+    349, 354, 359, 364, 369, 374, 379, 384, 389, 395, 400, 406, 411, 417, 423, 429,
+    435, 441, 447, 454, 461, 467, 475, 482, 489, 497, 505, 513, 522, 530, 539, 549,
+    559, 569, 579, 590, 602, 614, 626, 640, 654, 668, 684, 700, 717, 736, 755, 775,
+    796, 819, 843, 869, 896, 925, 955, 988, 1022, 1058, 1098, 1139, 1184, 1232, 1282, 1336,
+];
+
+const DC_QLOOKUP_10: [u16; 256] = [
+    4, 9, 10, 13, 15, 17, 20, 22, 25, 28, 31, 34, 37, 40, 43, 47,
+    50, 53, 57, 60, 64, 68, 71, 75, 78, 82, 86, 90, 93, 97, 101, 105,
+    109, 113, 116, 120, 124, 128, 132, 136, 140, 143, 147, 151, 155, 159, 163, 166,
+    170, 174, 178, 182, 185, 189, 193, 197, 200, 204, 208, 212, 215, 219, 223, 226,
+    230, 233, 237, 241, 244, 248, 251, 255, 259, 262, 266, 269, 273, 276, 280, 283,
+    287, 290, 293, 297, 300, 304, 307, 310, 314, 317, 321, 324, 327, 331, 334, 337,
+    343, 350, 356, 362, 369, 375, 381, 387, 394, 400, 406, 412, 418, 424, 430, 436,
+    442, 448, 454, 460, 466, 472, 478, 484, 490, 499, 507, 516, 525, 533, 542, 550,
+    559, 567, 576, 584, 592, 601, 609, 617, 625, 634, 644, 655, 666, 676, 687, 698,
+    708, 718, 729, 739, 749, 759, 770, 782, 795, 807, 819, 831, 844, 856, 868, 880,
+    891, 906, 920, 933, 947, 961, 975, 988, 1001, 1015, 1030, 1045, 1061, 1076, 1090, 1105,
+    1120, 1137, 1153, 1170, 1186, 1202, 1218, 1236, 1253, 1271, 1288, 1306, 1323, 1342, 1361, 1379,
+    1398, 1416, 1436, 1456, 1476, 1496, 1516, 1537, 1559, 1580, 1601, 1624, 1647, 1670, 1692, 1717,
+    1741, 1766, 1791, 1817, 1844, 1871, 1900, 1929, 1958, 1990, 2021, 2054, 2088, 2123, 2159, 2197,
+    2236, 2276, 2319, 2363, 2410, 2458, 2508, 2561, 2616, 2675, 2737, 2802, 2871, 2944, 3020, 3102,
+    3188, 3280, 3375, 3478, 3586, 3702, 3823, 3953, 4089, 4236, 4394, 4559, 4737, 4929, 5130, 5347,
+];
+
+const DC_QLOOKUP_12: [u16; 256] = [
+    4, 12, 18, 25, 33, 41, 50, 60, 70, 80, 91, 103, 115, 127, 140, 153,
+    166, 180, 194, 208, 222, 237, 251, 266, 281, 296, 312, 327, 343, 358, 374, 390,
+    405, 421, 437, 453, 469, 484, 500, 516, 532, 548, 564, 580, 596, 611, 627, 643,
+    659, 674, 690, 706, 721, 737, 752, 768, 783, 798, 814, 829, 844, 859, 874, 889,
+    904, 919, 934, 949, 964, 978, 993, 1008, 1022, 1037, 1051, 1065, 1080, 1094, 1108, 1122,
+    1136, 1151, 1165, 1179, 1192, 1206, 1220, 1234, 1248, 1261, 1275, 1288, 1302, 1315, 1329, 1342,
+    1368, 1393, 1419, 1444, 1469, 1494, 1519, 1544, 1569, 1594, 1618, 1643, 1668, 1692, 1717, 1741,
+    1765, 1789, 1814, 1838, 1862, 1885, 1909, 1933, 1957, 1992, 2027, 2061, 2096, 2130, 2165, 2199,
+    2233, 2267, 2300, 2334, 2367, 2400, 2434, 2467, 2499, 2532, 2575, 2618, 2661, 2704, 2746, 2788,
+    2830, 2872, 2913, 2954, 2995, 3036, 3076, 3127, 3177, 3226, 3275, 3324, 3373, 3421, 3469, 3517,
+    3565, 3621, 3677, 3733, 3788, 3843, 3897, 3951, 4005, 4058, 4119, 4181, 4241, 4301, 4361, 4420,
+    4479, 4546, 4612, 4677, 4742, 4807, 4871, 4942, 5013, 5083, 5153, 5222, 5291, 5367, 5442, 5517,
+    5591, 5665, 5745, 5825, 5905, 5984, 6063, 6149, 6234, 6319, 6404, 6495, 6587, 6678, 6769, 6867,
+    6966, 7064, 7163, 7269, 7376, 7483, 7599, 7715, 7832, 7958, 8085, 8214, 8352, 8492, 8635, 8788,
+    8945, 9104, 9275, 9450, 9639, 9832, 10031, 10245, 10465, 10702, 10946, 11210, 11482, 11776, 12081, 12409,
+    12750, 13118, 13501, 13913, 14343, 14807, 15290, 15812, 16356, 16943, 17575, 18237, 18949, 19718, 20521, 21387,
+];
+
+const AC_QLOOKUP: [u16; 256] = [
+    4, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22,
+    23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38,
+    39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54,
+    55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70,
+    71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86,
+    87, 88, 89, 90, 91, 92, 93, 94, 95, 96, 97, 98, 99, 100, 101, 102,
+    104, 106, 108, 110, 112, 114, 116, 118, 120, 122, 124, 126, 128, 130, 132, 134,
+    136, 138, 140, 142, 144, 146, 148, 150, 152, 155, 158, 161, 164, 167, 170, 173,
+    176, 179, 182, 185, 188, 191, 194, 197, 200, 203, 207, 211, 215, 219, 223, 227,
+    231, 235, 239, 243, 247, 251, 255, 260, 265, 270, 275, 280, 285, 290, 295, 300,
+    305, 311, 317, 323, 329, 335, 341, 347, 353, 359, 366, 373, 380, 387, 394, 401,
+    408, 416, 424, 432, 440, 448, 456, 465, 474, 483, 492, 501, 510, 520, 530, 540,
+    550, 560, 571, 582, 593, 604, 615, 627, 639, 651, 663, 676, 689, 702, 715, 729,
+    743, 757, 771, 786, 801, 816, 832, 848, 864, 881, 898, 915, 933, 951, 969, 988,
+    1007, 1026, 1046, 1066, 1087, 1108, 1129, 1151, 1173, 1196, 1219, 1243, 1267, 1292, 1317, 1343,
+    1369, 1396, 1423, 1451, 1479, 1508, 1537, 1567, 1597, 1628, 1660, 1692, 1725, 1759, 1793, 1828,
+];
+
+const AC_QLOOKUP_10: [u16; 256] = [
+    4, 9, 11, 13, 16, 18, 21, 24, 27, 30, 33, 37, 40, 44, 48, 51,
+    55, 59, 63, 67, 71, 75, 79, 83, 88, 92, 96, 100, 105, 109, 114, 118,
+    122, 127, 131, 136, 140, 145, 149, 154, 158, 163, 168, 172, 177, 181, 186, 190,
+    195, 199, 204, 208, 213, 217, 222, 226, 231, 235, 240, 244, 249, 253, 258, 262,
+    267, 271, 275, 280, 284, 289, 293, 297, 302, 306, 311, 315, 319, 324, 328, 332,
+    337, 341, 345, 349, 354, 358, 362, 367, 371, 375, 379, 384, 388, 392, 396, 401,
+    409, 417, 425, 433, 441, 449, 458, 466, 474, 482, 490, 498, 506, 514, 523, 531,
+    539, 547, 555, 563, 571, 579, 588, 596, 604, 616, 628, 640, 652, 664, 676, 688,
+    700, 713, 725, 737, 749, 761, 773, 785, 797, 809, 825, 841, 857, 873, 889, 905,
+    922, 938, 954, 970, 986, 1002, 1018, 1038, 1058, 1078, 1098, 1118, 1138, 1158, 1178, 1198,
+    1218, 1242, 1266, 1290, 1314, 1338, 1362, 1386, 1411, 1435, 1463, 1491, 1519, 1547, 1575, 1603,
+    1631, 1663, 1695, 1727, 1759, 1791, 1823, 1859, 1895, 1931, 1967, 2003, 2039, 2079, 2119, 2159,
+    2199, 2239, 2283, 2327, 2371, 2415, 2459, 2507, 2555, 2603, 2651, 2703, 2755, 2807, 2859, 2915,
+    2971, 3027, 3083, 3143, 3203, 3263, 3327, 3391, 3455, 3523, 3591, 3659, 3731, 3803, 3876, 3952,
+    4028, 4104, 4184, 4264, 4348, 4432, 4516, 4604, 4692, 4784, 4876, 4972, 5068, 5168, 5268, 5372,
+    5476, 5584, 5692, 5804, 5916, 6032, 6148, 6268, 6388, 6512, 6640, 6768, 6900, 7036, 7172, 7312,
+];
+
+const AC_QLOOKUP_12: [u16; 256] = [
+    4, 13, 19, 27, 35, 44, 54, 64, 75, 87, 99, 112, 126, 139, 154, 168,
+    183, 199, 214, 230, 247, 263, 280, 297, 314, 331, 349, 366, 384, 402, 420, 438,
+    456, 475, 493, 511, 530, 548, 567, 586, 604, 623, 642, 660, 679, 698, 716, 735,
+    753, 772, 791, 809, 828, 846, 865, 884, 902, 920, 939, 957, 976, 994, 1012, 1030,
+    1049, 1067, 1085, 1103, 1121, 1139, 1157, 1175, 1193, 1211, 1229, 1246, 1264, 1282, 1299, 1317,
+    1335, 1352, 1370, 1387, 1405, 1422, 1440, 1457, 1474, 1491, 1509, 1526, 1543, 1560, 1577, 1595,
+    1627, 1660, 1693, 1725, 1758, 1791, 1824, 1856, 1889, 1922, 1954, 1987, 2020, 2052, 2085, 2118,
+    2150, 2183, 2216, 2248, 2281, 2313, 2346, 2378, 2411, 2459, 2508, 2556, 2605, 2653, 2701, 2750,
+    2798, 2847, 2895, 2943, 2992, 3040, 3088, 3137, 3185, 3234, 3298, 3362, 3426, 3491, 3555, 3619,
+    3684, 3748, 3812, 3876, 3941, 4005, 4069, 4149, 4230, 4310, 4390, 4470, 4550, 4631, 4711, 4791,
+    4871, 4967, 5064, 5160, 5256, 5352, 5448, 5544, 5641, 5737, 5849, 5961, 6073, 6185, 6297, 6410,
+    6522, 6650, 6778, 6906, 7034, 7162, 7290, 7435, 7579, 7723, 7867, 8011, 8155, 8315, 8475, 8635,
+    8795, 8956, 9132, 9308, 9484, 9660, 9836, 10028, 10220, 10412, 10604, 10812, 11020, 11228, 11437, 11661,
+    11885, 12109, 12333, 12573, 12813, 13053, 13309, 13565, 13821, 14093, 14365, 14637, 14925, 15213, 15502, 15806,
+    16110, 16414, 16734, 17054, 17390, 17726, 18062, 18414, 18766, 19134, 19502, 19886, 20270, 20670, 21070, 21486,
+    21902, 22334, 22766, 23214, 23662, 24126, 24590, 25070, 25551, 26047, 26559, 27071, 27599, 28143, 28687, 29247,
+];
+
+pub(super) const SUBPEL_FILTERS: [[[i16; 8]; 16]; 6] = [
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [0, 2, -6, 126, 8, -2, 0, 0],
+        [0, 2, -10, 122, 18, -4, 0, 0],
+        [0, 2, -12, 116, 28, -8, 2, 0],
+        [0, 2, -14, 110, 38, -10, 2, 0],
+        [0, 2, -14, 102, 48, -12, 2, 0],
+        [0, 2, -16, 94, 58, -12, 2, 0],
+        [0, 2, -14, 84, 66, -12, 2, 0],
+        [0, 2, -14, 76, 76, -14, 2, 0],
+        [0, 2, -12, 66, 84, -14, 2, 0],
+        [0, 2, -12, 58, 94, -16, 2, 0],
+        [0, 2, -12, 48, 102, -14, 2, 0],
+        [0, 2, -10, 38, 110, -14, 2, 0],
+        [0, 2, -8, 28, 116, -12, 2, 0],
+        [0, 0, -4, 18, 122, -10, 2, 0],
+        [0, 0, -2, 8, 126, -6, 2, 0],
+    ],
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [0, 2, 28, 62, 34, 2, 0, 0],
+        [0, 0, 26, 62, 36, 4, 0, 0],
+        [0, 0, 22, 62, 40, 4, 0, 0],
+        [0, 0, 20, 60, 42, 6, 0, 0],
+        [0, 0, 18, 58, 44, 8, 0, 0],
+        [0, 0, 16, 56, 46, 10, 0, 0],
+        [0, -2, 16, 54, 48, 12, 0, 0],
+        [0, -2, 14, 52, 52, 14, -2, 0],
+        [0, 0, 12, 48, 54, 16, -2, 0],
+        [0, 0, 10, 46, 56, 16, 0, 0],
+        [0, 0, 8, 44, 58, 18, 0, 0],
+        [0, 0, 6, 42, 60, 20, 0, 0],
+        [0, 0, 4, 40, 62, 22, 0, 0],
+        [0, 0, 4, 36, 62, 26, 0, 0],
+        [0, 0, 2, 34, 62, 28, 2, 0],
+    ],
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [-2, 2, -6, 126, 8, -2, 2, 0],
+        [-2, 6, -12, 124, 16, -6, 4, -2],
+        [-2, 8, -18, 120, 26, -10, 6, -2],
+        [-4, 10, -22, 116, 38, -14, 6, -2],
+        [-4, 10, -22, 108, 48, -18, 8, -2],
+        [-4, 10, -24, 100, 60, -20, 8, -2],
+        [-4, 10, -24, 90, 70, -22, 10, -2],
+        [-4, 12, -24, 80, 80, -24, 12, -4],
+        [-2, 10, -22, 70, 90, -24, 10, -4],
+        [-2, 8, -20, 60, 100, -24, 10, -4],
+        [-2, 8, -18, 48, 108, -22, 10, -4],
+        [-2, 6, -14, 38, 116, -22, 10, -4],
+        [-2, 6, -10, 26, 120, -18, 8, -2],
+        [-2, 4, -6, 16, 124, -12, 6, -2],
+        [0, 2, -2, 8, 126, -6, 2, -2],
+    ],
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [0, 0, 0, 120, 8, 0, 0, 0],
+        [0, 0, 0, 112, 16, 0, 0, 0],
+        [0, 0, 0, 104, 24, 0, 0, 0],
+        [0, 0, 0, 96, 32, 0, 0, 0],
+        [0, 0, 0, 88, 40, 0, 0, 0],
+        [0, 0, 0, 80, 48, 0, 0, 0],
+        [0, 0, 0, 72, 56, 0, 0, 0],
+        [0, 0, 0, 64, 64, 0, 0, 0],
+        [0, 0, 0, 56, 72, 0, 0, 0],
+        [0, 0, 0, 48, 80, 0, 0, 0],
+        [0, 0, 0, 40, 88, 0, 0, 0],
+        [0, 0, 0, 32, 96, 0, 0, 0],
+        [0, 0, 0, 24, 104, 0, 0, 0],
+        [0, 0, 0, 16, 112, 0, 0, 0],
+        [0, 0, 0, 8, 120, 0, 0, 0],
+    ],
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [0, 0, -4, 126, 8, -2, 0, 0],
+        [0, 0, -8, 122, 18, -4, 0, 0],
+        [0, 0, -10, 116, 28, -6, 0, 0],
+        [0, 0, -12, 110, 38, -8, 0, 0],
+        [0, 0, -12, 102, 48, -10, 0, 0],
+        [0, 0, -14, 94, 58, -10, 0, 0],
+        [0, 0, -12, 84, 66, -10, 0, 0],
+        [0, 0, -12, 76, 76, -12, 0, 0],
+        [0, 0, -10, 66, 84, -12, 0, 0],
+        [0, 0, -10, 58, 94, -14, 0, 0],
+        [0, 0, -10, 48, 102, -12, 0, 0],
+        [0, 0, -8, 38, 110, -12, 0, 0],
+        [0, 0, -6, 28, 116, -10, 0, 0],
+        [0, 0, -4, 18, 122, -8, 0, 0],
+        [0, 0, -2, 8, 126, -4, 0, 0],
+    ],
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [0, 0, 30, 62, 34, 2, 0, 0],
+        [0, 0, 26, 62, 36, 4, 0, 0],
+        [0, 0, 22, 62, 40, 4, 0, 0],
+        [0, 0, 20, 60, 42, 6, 0, 0],
+        [0, 0, 18, 58, 44, 8, 0, 0],
+        [0, 0, 16, 56, 46, 10, 0, 0],
+        [0, 0, 14, 54, 48, 12, 0, 0],
+        [0, 0, 12, 52, 52, 12, 0, 0],
+        [0, 0, 12, 48, 54, 14, 0, 0],
+        [0, 0, 10, 46, 56, 16, 0, 0],
+        [0, 0, 8, 44, 58, 18, 0, 0],
+        [0, 0, 6, 42, 60, 20, 0, 0],
+        [0, 0, 4, 40, 62, 22, 0, 0],
+        [0, 0, 4, 36, 62, 26, 0, 0],
+        [0, 0, 2, 34, 62, 30, 0, 0],
+    ],
+];
+
+const SM_WEIGHTS: [u8; 128] = [
+    0, 0, 255, 128, 255, 149, 85, 64, 255, 197, 146, 105, 73, 50, 37, 32,
+    255, 225, 196, 170, 145, 123, 102, 84, 68, 54, 43, 33, 26, 20, 17, 16,
+    255, 240, 225, 210, 196, 182, 169, 157, 145, 133, 122, 111, 101, 92, 83, 74,
+    66, 59, 52, 45, 39, 34, 29, 25, 21, 17, 14, 12, 10, 9, 8, 8,
+    255, 248, 240, 233, 225, 218, 210, 203, 196, 189, 182, 176, 169, 163, 156, 150,
+    144, 138, 133, 127, 121, 116, 111, 106, 101, 96, 91, 86, 82, 77, 73, 69,
+    65, 61, 57, 54, 50, 47, 44, 41, 38, 35, 32, 29, 27, 25, 22, 20,
+    18, 16, 15, 13, 12, 10, 9, 8, 7, 6, 6, 5, 5, 4, 4, 4,
+];
+
+
+pub(super) const WARPED_FILTERS: [[i8; 8]; 193] = [
+    [0, 0, 127, 1, 0, 0, 0, 0],
+    [0, -1, 127, 2, 0, 0, 0, 0],
+    [1, -3, 127, 4, -1, 0, 0, 0],
+    [1, -4, 126, 6, -2, 1, 0, 0],
+    [1, -5, 126, 8, -3, 1, 0, 0],
+    [1, -6, 125, 11, -4, 1, 0, 0],
+    [1, -7, 124, 13, -4, 1, 0, 0],
+    [2, -8, 123, 15, -5, 1, 0, 0],
+    [2, -9, 122, 18, -6, 1, 0, 0],
+    [2, -10, 121, 20, -6, 1, 0, 0],
+    [2, -11, 120, 22, -7, 2, 0, 0],
+    [2, -12, 119, 25, -8, 2, 0, 0],
+    [3, -13, 117, 27, -8, 2, 0, 0],
+    [3, -13, 116, 29, -9, 2, 0, 0],
+    [3, -14, 114, 32, -10, 3, 0, 0],
+    [3, -15, 113, 35, -10, 2, 0, 0],
+    [3, -15, 111, 37, -11, 3, 0, 0],
+    [3, -16, 109, 40, -11, 3, 0, 0],
+    [3, -16, 108, 42, -12, 3, 0, 0],
+    [4, -17, 106, 45, -13, 3, 0, 0],
+    [4, -17, 104, 47, -13, 3, 0, 0],
+    [4, -17, 102, 50, -14, 3, 0, 0],
+    [4, -17, 100, 52, -14, 3, 0, 0],
+    [4, -18, 98, 55, -15, 4, 0, 0],
+    [4, -18, 96, 58, -15, 3, 0, 0],
+    [4, -18, 94, 60, -16, 4, 0, 0],
+    [4, -18, 91, 63, -16, 4, 0, 0],
+    [4, -18, 89, 65, -16, 4, 0, 0],
+    [4, -18, 87, 68, -17, 4, 0, 0],
+    [4, -18, 85, 70, -17, 4, 0, 0],
+    [4, -18, 82, 73, -17, 4, 0, 0],
+    [4, -18, 80, 75, -17, 4, 0, 0],
+    [4, -18, 78, 78, -18, 4, 0, 0],
+    [4, -17, 75, 80, -18, 4, 0, 0],
+    [4, -17, 73, 82, -18, 4, 0, 0],
+    [4, -17, 70, 85, -18, 4, 0, 0],
+    [4, -17, 68, 87, -18, 4, 0, 0],
+    [4, -16, 65, 89, -18, 4, 0, 0],
+    [4, -16, 63, 91, -18, 4, 0, 0],
+    [4, -16, 60, 94, -18, 4, 0, 0],
+    [3, -15, 58, 96, -18, 4, 0, 0],
+    [4, -15, 55, 98, -18, 4, 0, 0],
+    [3, -14, 52, 100, -17, 4, 0, 0],
+    [3, -14, 50, 102, -17, 4, 0, 0],
+    [3, -13, 47, 104, -17, 4, 0, 0],
+    [3, -13, 45, 106, -17, 4, 0, 0],
+    [3, -12, 42, 108, -16, 3, 0, 0],
+    [3, -11, 40, 109, -16, 3, 0, 0],
+    [3, -11, 37, 111, -15, 3, 0, 0],
+    [2, -10, 35, 113, -15, 3, 0, 0],
+    [3, -10, 32, 114, -14, 3, 0, 0],
+    [2, -9, 29, 116, -13, 3, 0, 0],
+    [2, -8, 27, 117, -13, 3, 0, 0],
+    [2, -8, 25, 119, -12, 2, 0, 0],
+    [2, -7, 22, 120, -11, 2, 0, 0],
+    [1, -6, 20, 121, -10, 2, 0, 0],
+    [1, -6, 18, 122, -9, 2, 0, 0],
+    [1, -5, 15, 123, -8, 2, 0, 0],
+    [1, -4, 13, 124, -7, 1, 0, 0],
+    [1, -4, 11, 125, -6, 1, 0, 0],
+    [1, -3, 8, 126, -5, 1, 0, 0],
+    [1, -2, 6, 126, -4, 1, 0, 0],
+    [0, -1, 4, 127, -3, 1, 0, 0],
+    [0, 0, 2, 127, -1, 0, 0, 0],
+    [0, 0, 0, 127, 1, 0, 0, 0],
+    [0, 0, -1, 127, 2, 0, 0, 0],
+    [0, 1, -3, 127, 4, -2, 1, 0],
+    [0, 1, -5, 127, 6, -2, 1, 0],
+    [0, 2, -6, 126, 8, -3, 1, 0],
+    [-1, 2, -7, 126, 11, -4, 2, -1],
+    [-1, 3, -8, 125, 13, -5, 2, -1],
+    [-1, 3, -10, 124, 16, -6, 3, -1],
+    [-1, 4, -11, 123, 18, -7, 3, -1],
+    [-1, 4, -12, 122, 20, -7, 3, -1],
+    [-1, 4, -13, 121, 23, -8, 3, -1],
+    [-2, 5, -14, 120, 25, -9, 4, -1],
+    [-1, 5, -15, 119, 27, -10, 4, -1],
+    [-1, 5, -16, 118, 30, -11, 4, -1],
+    [-2, 6, -17, 116, 33, -12, 5, -1],
+    [-2, 6, -17, 114, 35, -12, 5, -1],
+    [-2, 6, -18, 113, 38, -13, 5, -1],
+    [-2, 7, -19, 111, 41, -14, 6, -2],
+    [-2, 7, -19, 110, 43, -15, 6, -2],
+    [-2, 7, -20, 108, 46, -15, 6, -2],
+    [-2, 7, -20, 106, 49, -16, 6, -2],
+    [-2, 7, -21, 104, 51, -16, 7, -2],
+    [-2, 7, -21, 102, 54, -17, 7, -2],
+    [-2, 8, -21, 100, 56, -18, 7, -2],
+    [-2, 8, -22, 98, 59, -18, 7, -2],
+    [-2, 8, -22, 96, 62, -19, 7, -2],
+    [-2, 8, -22, 94, 64, -19, 7, -2],
+    [-2, 8, -22, 91, 67, -20, 8, -2],
+    [-2, 8, -22, 89, 69, -20, 8, -2],
+    [-2, 8, -22, 87, 72, -21, 8, -2],
+    [-2, 8, -21, 84, 74, -21, 8, -2],
+    [-2, 8, -22, 82, 77, -21, 8, -2],
+    [-2, 8, -21, 79, 79, -21, 8, -2],
+    [-2, 8, -21, 77, 82, -22, 8, -2],
+    [-2, 8, -21, 74, 84, -21, 8, -2],
+    [-2, 8, -21, 72, 87, -22, 8, -2],
+    [-2, 8, -20, 69, 89, -22, 8, -2],
+    [-2, 8, -20, 67, 91, -22, 8, -2],
+    [-2, 7, -19, 64, 94, -22, 8, -2],
+    [-2, 7, -19, 62, 96, -22, 8, -2],
+    [-2, 7, -18, 59, 98, -22, 8, -2],
+    [-2, 7, -18, 56, 100, -21, 8, -2],
+    [-2, 7, -17, 54, 102, -21, 7, -2],
+    [-2, 7, -16, 51, 104, -21, 7, -2],
+    [-2, 6, -16, 49, 106, -20, 7, -2],
+    [-2, 6, -15, 46, 108, -20, 7, -2],
+    [-2, 6, -15, 43, 110, -19, 7, -2],
+    [-2, 6, -14, 41, 111, -19, 7, -2],
+    [-1, 5, -13, 38, 113, -18, 6, -2],
+    [-1, 5, -12, 35, 114, -17, 6, -2],
+    [-1, 5, -12, 33, 116, -17, 6, -2],
+    [-1, 4, -11, 30, 118, -16, 5, -1],
+    [-1, 4, -10, 27, 119, -15, 5, -1],
+    [-1, 4, -9, 25, 120, -14, 5, -2],
+    [-1, 3, -8, 23, 121, -13, 4, -1],
+    [-1, 3, -7, 20, 122, -12, 4, -1],
+    [-1, 3, -7, 18, 123, -11, 4, -1],
+    [-1, 3, -6, 16, 124, -10, 3, -1],
+    [-1, 2, -5, 13, 125, -8, 3, -1],
+    [-1, 2, -4, 11, 126, -7, 2, -1],
+    [0, 1, -3, 8, 126, -6, 2, 0],
+    [0, 1, -2, 6, 127, -5, 1, 0],
+    [0, 1, -2, 4, 127, -3, 1, 0],
+    [0, 0, 0, 2, 127, -1, 0, 0],
+    [0, 0, 0, 1, 127, 0, 0, 0],
+    [0, 0, 0, -1, 127, 2, 0, 0],
+    [0, 0, 1, -3, 127, 4, -1, 0],
+    [0, 0, 1, -4, 126, 6, -2, 1],
+    [0, 0, 1, -5, 126, 8, -3, 1],
+    [0, 0, 1, -6, 125, 11, -4, 1],
+    [0, 0, 1, -7, 124, 13, -4, 1],
+    [0, 0, 2, -8, 123, 15, -5, 1],
+    [0, 0, 2, -9, 122, 18, -6, 1],
+    [0, 0, 2, -10, 121, 20, -6, 1],
+    [0, 0, 2, -11, 120, 22, -7, 2],
+    [0, 0, 2, -12, 119, 25, -8, 2],
+    [0, 0, 3, -13, 117, 27, -8, 2],
+    [0, 0, 3, -13, 116, 29, -9, 2],
+    [0, 0, 3, -14, 114, 32, -10, 3],
+    [0, 0, 3, -15, 113, 35, -10, 2],
+    [0, 0, 3, -15, 111, 37, -11, 3],
+    [0, 0, 3, -16, 109, 40, -11, 3],
+    [0, 0, 3, -16, 108, 42, -12, 3],
+    [0, 0, 4, -17, 106, 45, -13, 3],
+    [0, 0, 4, -17, 104, 47, -13, 3],
+    [0, 0, 4, -17, 102, 50, -14, 3],
+    [0, 0, 4, -17, 100, 52, -14, 3],
+    [0, 0, 4, -18, 98, 55, -15, 4],
+    [0, 0, 4, -18, 96, 58, -15, 3],
+    [0, 0, 4, -18, 94, 60, -16, 4],
+    [0, 0, 4, -18, 91, 63, -16, 4],
+    [0, 0, 4, -18, 89, 65, -16, 4],
+    [0, 0, 4, -18, 87, 68, -17, 4],
+    [0, 0, 4, -18, 85, 70, -17, 4],
+    [0, 0, 4, -18, 82, 73, -17, 4],
+    [0, 0, 4, -18, 80, 75, -17, 4],
+    [0, 0, 4, -18, 78, 78, -18, 4],
+    [0, 0, 4, -17, 75, 80, -18, 4],
+    [0, 0, 4, -17, 73, 82, -18, 4],
+    [0, 0, 4, -17, 70, 85, -18, 4],
+    [0, 0, 4, -17, 68, 87, -18, 4],
+    [0, 0, 4, -16, 65, 89, -18, 4],
+    [0, 0, 4, -16, 63, 91, -18, 4],
+    [0, 0, 4, -16, 60, 94, -18, 4],
+    [0, 0, 3, -15, 58, 96, -18, 4],
+    [0, 0, 4, -15, 55, 98, -18, 4],
+    [0, 0, 3, -14, 52, 100, -17, 4],
+    [0, 0, 3, -14, 50, 102, -17, 4],
+    [0, 0, 3, -13, 47, 104, -17, 4],
+    [0, 0, 3, -13, 45, 106, -17, 4],
+    [0, 0, 3, -12, 42, 108, -16, 3],
+    [0, 0, 3, -11, 40, 109, -16, 3],
+    [0, 0, 3, -11, 37, 111, -15, 3],
+    [0, 0, 2, -10, 35, 113, -15, 3],
+    [0, 0, 3, -10, 32, 114, -14, 3],
+    [0, 0, 2, -9, 29, 116, -13, 3],
+    [0, 0, 2, -8, 27, 117, -13, 3],
+    [0, 0, 2, -8, 25, 119, -12, 2],
+    [0, 0, 2, -7, 22, 120, -11, 2],
+    [0, 0, 1, -6, 20, 121, -10, 2],
+    [0, 0, 1, -6, 18, 122, -9, 2],
+    [0, 0, 1, -5, 15, 123, -8, 2],
+    [0, 0, 1, -4, 13, 124, -7, 1],
+    [0, 0, 1, -4, 11, 125, -6, 1],
+    [0, 0, 1, -3, 8, 126, -5, 1],
+    [0, 0, 1, -2, 6, 126, -4, 1],
+    [0, 0, 0, -1, 4, 127, -3, 1],
+    [0, 0, 0, 0, 2, 127, -1, 0],
+    [0, 0, 0, 0, 2, 127, -1, 0],
+];
+
+pub(super) const FILTER_INTRA_TAPS: [[[i8; 7]; 8]; 5] = [
+    [
+        [-6, 10, 0, 0, 0, 12, 0],
+        [-5, 2, 10, 0, 0, 9, 0],
+        [-3, 1, 1, 10, 0, 7, 0],
+        [-3, 1, 1, 2, 10, 5, 0],
+        [-4, 6, 0, 0, 0, 2, 12],
+        [-3, 2, 6, 0, 0, 2, 9],
+        [-3, 2, 2, 6, 0, 2, 7],
+        [-3, 1, 2, 2, 6, 3, 5],
+    ],
+    [
+        [-10, 16, 0, 0, 0, 10, 0],
+        [-6, 0, 16, 0, 0, 6, 0],
+        [-4, 0, 0, 16, 0, 4, 0],
+        [-2, 0, 0, 0, 16, 2, 0],
+        [-10, 16, 0, 0, 0, 0, 10],
+        [-6, 0, 16, 0, 0, 0, 6],
+        [-4, 0, 0, 16, 0, 0, 4],
+        [-2, 0, 0, 0, 16, 0, 2],
+    ],
+    [
+        [-8, 8, 0, 0, 0, 16, 0],
+        [-8, 0, 8, 0, 0, 16, 0],
+        [-8, 0, 0, 8, 0, 16, 0],
+        [-8, 0, 0, 0, 8, 16, 0],
+        [-4, 4, 0, 0, 0, 0, 16],
+        [-4, 0, 4, 0, 0, 0, 16],
+        [-4, 0, 0, 4, 0, 0, 16],
+        [-4, 0, 0, 0, 4, 0, 16],
+    ],
+    [
+        [-2, 8, 0, 0, 0, 10, 0],
+        [-1, 3, 8, 0, 0, 6, 0],
+        [-1, 2, 3, 8, 0, 4, 0],
+        [0, 1, 2, 3, 8, 2, 0],
+        [-1, 4, 0, 0, 0, 3, 10],
+        [-1, 3, 4, 0, 0, 4, 6],
+        [-1, 2, 3, 4, 0, 4, 4],
+        [-1, 2, 2, 3, 4, 3, 3],
+    ],
+    [
+        [-12, 14, 0, 0, 0, 14, 0],
+        [-10, 0, 14, 0, 0, 12, 0],
+        [-9, 0, 0, 14, 0, 11, 0],
+        [-8, 0, 0, 0, 14, 10, 0],
+        [-10, 12, 0, 0, 0, 0, 14],
+        [-9, 1, 12, 0, 0, 0, 12],
+        [-8, 0, 0, 12, 0, 1, 11],
+        [-7, 0, 0, 1, 12, 1, 9],
+    ],
+];
+
+const OBMC_MASKS: [u8; 64] = [
+    64, 64, 45, 64, 39, 50, 59, 64, 36, 42, 48, 53, 57, 61, 64, 64,
+    34, 37, 40, 43, 46, 49, 52, 54, 56, 58, 60, 61, 64, 64, 64, 64,
+    33, 35, 36, 38, 40, 41, 43, 44, 45, 47, 48, 50, 51, 52, 53, 55,
+    56, 57, 58, 59, 60, 60, 61, 62, 64, 64, 64, 64, 64, 64, 64, 64,
+];
+
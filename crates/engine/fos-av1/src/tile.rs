@@ -0,0 +1,1768 @@
+//! AV1 Tile Decoding
+//!
+//! Partition trees, mode info and reconstruction of one tile. Tiles only
+//! look at their own mode info and samples, so each one decodes into its
+//! own buffers and may run on a separate thread.
+
+use super::cdf::CdfContext;
+use super::headers::*;
+use super::mvpred::MotionField;
+use super::state::{Grid, MiInfo, Mv, PlaneBuf, RefFrame};
+use super::symbol::SymbolDecoder;
+use super::tables::*;
+use crate::{Av1Error, Av1Result};
+use std::sync::Arc;
+
+pub(super) const NONE_FRAME: i8 = -1;
+
+const SIMPLE: u8 = 0;
+pub(super) const OBMC: u8 = 1;
+pub(super) const LOCALWARP: u8 = 2;
+
+pub(super) const COMPOUND_WEDGE: u8 = 0;
+pub(super) const COMPOUND_DIFFWTD: u8 = 1;
+pub(super) const COMPOUND_AVERAGE: u8 = 2;
+pub(super) const COMPOUND_INTRA: u8 = 3;
+pub(super) const COMPOUND_DISTANCE: u8 = 4;
+
+const DELTA_Q_SMALL: usize = 3;
+const DELTA_LF_SMALL: usize = 3;
+const MAX_LOOP_FILTER: i32 = 63;
+const MAX_VARTX_DEPTH: u32 = 2;
+/// Bounds, exclusive, of motion vector components and their coded
+/// differences
+pub(super) const MV_LOW: i32 = -(1 << 14);
+pub(super) const MV_UPP: i32 = 1 << 14;
+
+/// Frame-level inputs shared by all tiles of a frame
+pub(super) struct FrameContext<'a> {
+    pub seq: &'a Av1SequenceHeader,
+    pub hdr: &'a FrameHeader,
+    /// Reference frames, indexed by LAST_FRAME..ALTREF_FRAME
+    pub refs: [Option<Arc<RefFrame>>; NUM_REF_FRAMES],
+    pub motion_field: Option<&'a MotionField>,
+    pub prev_segment_ids: Option<&'a Grid<u8>>,
+    pub cdfs: &'a CdfContext,
+    pub bit_depth: u8,
+    pub ss_x: bool,
+    pub ss_y: bool,
+    pub num_planes: usize,
+    /// Aligned dimensions of the sample planes
+    pub plane_widths: [usize; 3],
+    pub plane_heights: [usize; 3],
+}
+
+impl FrameContext<'_> {
+    pub fn sb_size(&self) -> usize {
+        if self.seq.use_128x128_superblock { BLOCK_128X128 } else { BLOCK_64X64 }
+    }
+
+    pub fn sub(&self, plane: usize) -> (usize, usize) {
+        if plane == 0 { (0, 0) } else { (self.ss_x as usize, self.ss_y as usize) }
+    }
+
+    pub fn seg_feature_active(&self, segment_id: u8, feature: usize) -> bool {
+        self.hdr.segmentation_enabled && self.hdr.feature_enabled[segment_id as usize][feature]
+    }
+}
+
+/// Loop restoration coefficients of one restoration unit
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct LrUnit {
+    pub kind: u8,
+    pub wiener: [[i32; 3]; 2],
+    pub sgr_set: u8,
+    pub sgr_xqd: [i32; 2],
+}
+
+/// Everything a tile produces for the frame-level stages
+pub(super) struct TileOutput {
+    pub planes: Vec<PlaneBuf>,
+    pub mi: Grid<MiInfo>,
+    /// Transform sizes for the loop filter, per plane in 4x4 units
+    pub lf_tx: Vec<Grid<u8>>,
+    /// CDEF strength index per 64x64 block, -1 when not coded
+    pub cdef_idx: Grid<i8>,
+    /// Coded restoration units: plane, unit row, unit column
+    pub lr_units: Vec<(usize, usize, usize, LrUnit)>,
+    pub cdfs: CdfContext,
+}
+
+/// State of the block being decoded
+#[derive(Default)]
+pub(super) struct Block {
+    pub mi_row: usize,
+    pub mi_col: usize,
+    pub mi_size: usize,
+    pub bw4: usize,
+    pub bh4: usize,
+    pub has_chroma: bool,
+    pub avail_u: bool,
+    pub avail_l: bool,
+    pub avail_u_chroma: bool,
+    pub avail_l_chroma: bool,
+    pub skip: bool,
+    pub skip_mode: bool,
+    pub segment_id: u8,
+    pub lossless: bool,
+    pub is_inter: bool,
+    pub use_intrabc: bool,
+    pub y_mode: u8,
+    pub uv_mode: u8,
+    pub angle_delta_y: i32,
+    pub angle_delta_uv: i32,
+    pub use_filter_intra: bool,
+    pub filter_intra_mode: u8,
+    pub cfl_alpha_u: i32,
+    pub cfl_alpha_v: i32,
+    pub palette_size_y: usize,
+    pub palette_size_uv: usize,
+    pub palette_colors: [[u16; 8]; 3],
+    pub color_map_y: Vec<u8>,
+    pub color_map_uv: Vec<u8>,
+    pub ref_frame: [i8; 2],
+    pub mv: [Mv; 2],
+    pub interp_filter: [u8; 2],
+    pub motion_mode: u8,
+    pub interintra: bool,
+    pub interintra_mode: u8,
+    pub wedge_interintra: bool,
+    pub wedge_index: usize,
+    pub wedge_sign: bool,
+    pub mask_type: bool,
+    pub compound_type: u8,
+    pub comp_group_idx: u8,
+    pub compound_idx: u8,
+    pub tx_size: usize,
+    pub ref_mv_idx: usize,
+    pub max_luma_w: usize,
+    pub max_luma_h: usize,
+    // Neighbour references
+    pub above_ref: [i8; 2],
+    pub left_ref: [i8; 2],
+    pub above_intra: bool,
+    pub left_intra: bool,
+    pub above_single: bool,
+    pub left_single: bool,
+    // Motion vector prediction
+    pub num_mv_found: usize,
+    pub new_mv_count: usize,
+    pub ref_stack_mv: [[Mv; 2]; 9],
+    pub weight_stack: [u32; 9],
+    pub global_mvs: [Mv; 2],
+    pub new_mv_context: usize,
+    pub ref_mv_context: usize,
+    pub zero_mv_context: usize,
+    pub drl_ctx_stack: [usize; 9],
+    pub pred_mv: [Mv; 2],
+    pub found_match: bool,
+    // Warped motion
+    pub num_samples: usize,
+    pub num_samples_scanned: usize,
+    pub cand_list: [[i32; 4]; 8],
+    pub local_warp_params: [i32; 6],
+    pub local_valid: bool,
+}
+
+/// Decoder of one tile
+pub(super) struct TileDecoder<'a> {
+    pub f: &'a FrameContext<'a>,
+    pub sd: SymbolDecoder<'a>,
+    pub cdf: CdfContext,
+    pub mi_row_start: usize,
+    pub mi_row_end: usize,
+    pub mi_col_start: usize,
+    pub mi_col_end: usize,
+    pub out: TileOutput,
+    pub palettes: Vec<[[u16; 8]; 2]>,
+    pub above_level: [Vec<u8>; 3],
+    pub above_dc: [Vec<u8>; 3],
+    pub left_level: [Vec<u8>; 3],
+    pub left_dc: [Vec<u8>; 3],
+    above_seg_pred: Vec<bool>,
+    left_seg_pred: Vec<bool>,
+    delta_lf: [i32; 4],
+    pub current_q_index: i32,
+    read_deltas: bool,
+    ref_sgr_xqd: [[i32; 2]; 3],
+    ref_lr_wiener: [[[i32; 3]; 2]; 3],
+    /// Decoded 4x4 units of the current superblock with a one unit border,
+    /// per plane
+    pub block_decoded: [[[bool; 34]; 34]; 3],
+    pub b: Block,
+    /// Why the tile data does not conform, once it is found not to
+    corrupt: Option<&'static str>,
+}
+
+pub(super) fn count_units_in_frame(unit_size: usize, frame_size: usize) -> usize {
+    ((frame_size + (unit_size >> 1)) / unit_size).max(1)
+}
+
+fn neg_deinterleave(diff: i32, r: i32, max: i32) -> i32 {
+    if r == 0 {
+        return diff;
+    }
+    if r >= max - 1 {
+        return max - diff - 1;
+    }
+    if 2 * r < max {
+        if diff <= 2 * r {
+            return if diff & 1 != 0 { r + ((diff + 1) >> 1) } else { r - (diff >> 1) };
+        }
+        diff
+    } else {
+        if diff <= 2 * (max - r - 1) {
+            return if diff & 1 != 0 { r + ((diff + 1) >> 1) } else { r - (diff >> 1) };
+        }
+        max - (diff + 1)
+    }
+}
+
+/// Sum of the probabilities of some symbols of a CDF
+fn cdf_prob_sum(cdf: &[u16], symbols: &[usize]) -> u32 {
+    symbols.iter().map(|&s| cdf[s] as u32 - if s > 0 { cdf[s - 1] as u32 } else { 0 }).sum()
+}
+
+impl<'a> TileDecoder<'a> {
+    pub fn new(f: &'a FrameContext<'a>, data: &'a [u8], tile_row: usize, tile_col: usize) -> Self {
+        let ti = &f.hdr.tile_info;
+        let (mi_row_start, mi_row_end) = (ti.mi_row_starts[tile_row], ti.mi_row_starts[tile_row + 1]);
+        let (mi_col_start, mi_col_end) = (ti.mi_col_starts[tile_col], ti.mi_col_starts[tile_col + 1]);
+        let sb4 = block_width4(f.sb_size());
+        // Blocks may reach past the frame edge up to the superblock edge
+        let rows4 = (mi_row_end - mi_row_start).next_multiple_of(sb4);
+        let cols4 = (mi_col_end - mi_col_start).next_multiple_of(sb4);
+        let planes = (0..f.num_planes)
+            .map(|p| {
+                let (sx, sy) = f.sub(p);
+                Grid::new((mi_row_start * 4) >> sy, (mi_col_start * 4) >> sx, (rows4 * 4) >> sy, (cols4 * 4) >> sx, 0)
+            })
+            .collect();
+        let lf_tx = (0..f.num_planes)
+            .map(|p| {
+                let (sx, sy) = f.sub(p);
+                Grid::new(mi_row_start >> sy, mi_col_start >> sx, rows4 >> sy, cols4 >> sx, 0)
+            })
+            .collect();
+        let allow_update = !f.hdr.disable_cdf_update;
+        let ctx_cols = f.hdr.mi_cols + 32;
+        let ctx_rows = f.hdr.mi_rows + 32;
+        Self {
+            f,
+            sd: SymbolDecoder::new(data, allow_update),
+            cdf: f.cdfs.clone(),
+            mi_row_start,
+            mi_row_end,
+            mi_col_start,
+            mi_col_end,
+            out: TileOutput {
+                planes,
+                mi: Grid::new(mi_row_start, mi_col_start, rows4, cols4, MiInfo::default()),
+                lf_tx,
+                cdef_idx: Grid::new(mi_row_start >> 4, mi_col_start >> 4, rows4.div_ceil(16), cols4.div_ceil(16), -1),
+                lr_units: Vec::new(),
+                cdfs: CdfContext::default(),
+            },
+            palettes: vec![[[0; 8]; 2]],
+            above_level: std::array::from_fn(|_| vec![0; ctx_cols]),
+            above_dc: std::array::from_fn(|_| vec![0; ctx_cols]),
+            left_level: std::array::from_fn(|_| vec![0; ctx_rows]),
+            left_dc: std::array::from_fn(|_| vec![0; ctx_rows]),
+            above_seg_pred: vec![false; ctx_cols],
+            left_seg_pred: vec![false; ctx_rows],
+            delta_lf: [0; 4],
+            current_q_index: f.hdr.base_q_idx,
+            read_deltas: false,
+            ref_sgr_xqd: [SGRPROJ_XQD_MID; 3],
+            ref_lr_wiener: [[WIENER_TAPS_MID; 2]; 3],
+            block_decoded: [[[false; 34]; 34]; 3],
+            b: Block::default(),
+            corrupt: None,
+        }
+    }
+
+    /// Decode every superblock of the tile, stopping at the first one
+    /// whose data does not conform
+    pub fn decode(mut self) -> Av1Result<TileOutput> {
+        let sb_size = self.f.sb_size();
+        let sb4 = block_width4(sb_size);
+        for r in (self.mi_row_start..self.mi_row_end).step_by(sb4) {
+            for plane in 0..3 {
+                self.left_level[plane].fill(0);
+                self.left_dc[plane].fill(0);
+            }
+            self.left_seg_pred.fill(false);
+            for c in (self.mi_col_start..self.mi_col_end).step_by(sb4) {
+                self.read_deltas = self.f.hdr.delta_q_present;
+                self.clear_block_decoded_flags(r, c, sb4);
+                self.read_lr(r, c, sb_size);
+                self.decode_partition(r, c, sb_size);
+                if self.sd.overrun() {
+                    self.set_corrupt("AV1 tile data ended early");
+                }
+                if let Some(msg) = self.corrupt {
+                    return Err(Av1Error::InvalidBitstream(msg.into()));
+                }
+            }
+        }
+        self.out.cdfs = self.cdf;
+        Ok(self.out)
+    }
+
+    /// Record that the tile data does not conform; decoding stops at the
+    /// end of the superblock
+    pub fn set_corrupt(&mut self, msg: &'static str) {
+        self.corrupt.get_or_insert(msg);
+    }
+
+    fn clear_block_decoded_flags(&mut self, r: usize, c: usize, sb4: usize) {
+        for plane in 0..self.f.num_planes {
+            let (sx, sy) = self.f.sub(plane);
+            let sb_w4 = ((self.mi_col_end - c) >> sx) as isize;
+            let sb_h4 = ((self.mi_row_end - r) >> sy) as isize;
+            let flags = &mut self.block_decoded[plane];
+            for y in -1..=(sb4 >> sy) as isize {
+                for x in -1..=(sb4 >> sx) as isize {
+                    flags[(y + 1) as usize][(x + 1) as usize] = (y < 0 && x < sb_w4) || (x < 0 && y < sb_h4);
+                }
+            }
+            flags[(sb4 >> sy) + 1][0] = false;
+        }
+    }
+
+    pub fn block_decoded(&self, plane: usize, row: isize, col: isize) -> bool {
+        self.block_decoded[plane][(row + 1) as usize][(col + 1) as usize]
+    }
+
+    pub fn is_inside(&self, row: isize, col: isize) -> bool {
+        col >= self.mi_col_start as isize
+            && col < self.mi_col_end as isize
+            && row >= self.mi_row_start as isize
+            && row < self.mi_row_end as isize
+    }
+
+    pub fn mi(&self, row: usize, col: usize) -> &MiInfo {
+        self.out.mi.get(row, col)
+    }
+
+    fn read_lr(&mut self, r: usize, c: usize, bsize: usize) {
+        let hdr = self.f.hdr;
+        if hdr.allow_intrabc {
+            return;
+        }
+        let (w, h) = (block_width4(bsize), block_height4(bsize));
+        for plane in 0..self.f.num_planes {
+            if hdr.frame_restoration_type[plane] == RESTORE_NONE {
+                continue;
+            }
+            let (sx, sy) = self.f.sub(plane);
+            let unit_size = hdr.loop_restoration_size[plane];
+            let unit_rows = count_units_in_frame(unit_size, (hdr.frame_height as usize + sy) >> sy);
+            let unit_cols = count_units_in_frame(unit_size, (hdr.upscaled_width as usize + sx) >> sx);
+            let row_start = (r * (4 >> sy)).div_ceil(unit_size);
+            let row_end = unit_rows.min(((r + h) * (4 >> sy)).div_ceil(unit_size));
+            let (num, den) = if hdr.use_superres {
+                ((4 >> sx) * hdr.superres_denom as usize, unit_size * 8)
+            } else {
+                (4 >> sx, unit_size)
+            };
+            let col_start = (c * num).div_ceil(den);
+            let col_end = unit_cols.min(((c + w) * num).div_ceil(den));
+            for unit_row in row_start..row_end {
+                for unit_col in col_start..col_end {
+                    let unit = self.read_lr_unit(plane);
+                    self.out.lr_units.push((plane, unit_row, unit_col, unit));
+                }
+            }
+        }
+    }
+
+    fn read_lr_unit(&mut self, plane: usize) -> LrUnit {
+        let kind = match self.f.hdr.frame_restoration_type[plane] {
+            RESTORE_WIENER => {
+                if self.sd.read_symbol(&mut self.cdf.use_wiener) == 1 { RESTORE_WIENER } else { RESTORE_NONE }
+            }
+            RESTORE_SGRPROJ => {
+                if self.sd.read_symbol(&mut self.cdf.use_sgrproj) == 1 { RESTORE_SGRPROJ } else { RESTORE_NONE }
+            }
+            _ => self.sd.read_symbol(&mut self.cdf.restoration_type) as u8,
+        };
+        let mut unit = LrUnit { kind, ..Default::default() };
+        if kind == RESTORE_WIENER {
+            for pass in 0..2 {
+                let first = (plane > 0) as usize;
+                for j in first..3 {
+                    let v = self.sd.decode_signed_subexp_with_ref_bool(
+                        WIENER_TAPS_MIN[j],
+                        WIENER_TAPS_MAX[j] + 1,
+                        WIENER_TAPS_K[j],
+                        self.ref_lr_wiener[plane][pass][j],
+                    );
+                    unit.wiener[pass][j] = v;
+                    self.ref_lr_wiener[plane][pass][j] = v;
+                }
+            }
+        } else if kind == RESTORE_SGRPROJ {
+            let set = self.sd.read_literal(4) as usize;
+            unit.sgr_set = set as u8;
+            for i in 0..2 {
+                let radius = SGR_PARAMS[set][i * 2];
+                let (min, max) = (SGRPROJ_XQD_MIN[i], SGRPROJ_XQD_MAX[i]);
+                let v = if radius != 0 {
+                    self.sd.decode_signed_subexp_with_ref_bool(min, max + 1, 4, self.ref_sgr_xqd[plane][i])
+                } else if i == 1 {
+                    ((1 << 7) - self.ref_sgr_xqd[plane][0]).clamp(min, max)
+                } else {
+                    0
+                };
+                unit.sgr_xqd[i] = v;
+                self.ref_sgr_xqd[plane][i] = v;
+            }
+        }
+        unit
+    }
+
+    fn decode_partition(&mut self, r: usize, c: usize, bsize: usize) {
+        let hdr = self.f.hdr;
+        if r >= hdr.mi_rows || c >= hdr.mi_cols {
+            return;
+        }
+        let avail_u = self.is_inside(r as isize - 1, c as isize);
+        let avail_l = self.is_inside(r as isize, c as isize - 1);
+        let num4x4 = block_width4(bsize);
+        let half = num4x4 >> 1;
+        let quarter = half >> 1;
+        let has_rows = r + half < hdr.mi_rows;
+        let has_cols = c + half < hdr.mi_cols;
+        let partition = if bsize < BLOCK_8X8 {
+            PARTITION_NONE
+        } else {
+            let bsl = BLOCK_WIDTH_LOG2[bsize] - 2;
+            let above = avail_u && BLOCK_WIDTH_LOG2[self.mi(r - 1, c).mi_size as usize] - 2 < bsl;
+            let left = avail_l && BLOCK_HEIGHT_LOG2[self.mi(r, c - 1).mi_size as usize] - 2 < bsl;
+            let ctx = left as usize * 2 + above as usize;
+            let cdf: &mut [u16] = match bsl {
+                1 => &mut self.cdf.partition_w8[ctx],
+                5 => &mut self.cdf.partition_w128[ctx],
+                _ => &mut self.cdf.partition[bsl as usize - 2][ctx],
+            };
+            let is_128 = bsize == BLOCK_128X128;
+            if has_rows && has_cols {
+                self.sd.read_symbol(cdf)
+            } else if has_cols {
+                // Only the top half is inside: anything splitting it
+                // vertically becomes a split
+                let mut symbols = vec![PARTITION_VERT, PARTITION_SPLIT, PARTITION_HORZ_A, PARTITION_VERT_A, PARTITION_VERT_B];
+                if !is_128 && bsl > 1 {
+                    symbols.push(PARTITION_VERT_4);
+                }
+                let p = cdf_prob_sum(cdf, &symbols).min(32768);
+                let bool_cdf = [(32768 - p) as u16, 32768, 0];
+                if self.sd.read_symbol_no_update(&bool_cdf) == 1 { PARTITION_SPLIT } else { PARTITION_HORZ }
+            } else if has_rows {
+                let mut symbols = vec![PARTITION_HORZ, PARTITION_SPLIT, PARTITION_HORZ_A, PARTITION_HORZ_B, PARTITION_VERT_A];
+                if !is_128 && bsl > 1 {
+                    symbols.push(PARTITION_HORZ_4);
+                }
+                let p = cdf_prob_sum(cdf, &symbols).min(32768);
+                let bool_cdf = [(32768 - p) as u16, 32768, 0];
+                if self.sd.read_symbol_no_update(&bool_cdf) == 1 { PARTITION_SPLIT } else { PARTITION_VERT }
+            } else {
+                PARTITION_SPLIT
+            }
+        };
+        let sub_size = partition_subsize(bsize, partition);
+        let split_size = partition_subsize(bsize, PARTITION_SPLIT);
+        match partition {
+            PARTITION_NONE => self.decode_block(r, c, sub_size),
+            PARTITION_HORZ => {
+                self.decode_block(r, c, sub_size);
+                if has_rows {
+                    self.decode_block(r + half, c, sub_size);
+                }
+            }
+            PARTITION_VERT => {
+                self.decode_block(r, c, sub_size);
+                if has_cols {
+                    self.decode_block(r, c + half, sub_size);
+                }
+            }
+            PARTITION_SPLIT => {
+                self.decode_partition(r, c, sub_size);
+                self.decode_partition(r, c + half, sub_size);
+                self.decode_partition(r + half, c, sub_size);
+                self.decode_partition(r + half, c + half, sub_size);
+            }
+            PARTITION_HORZ_A => {
+                self.decode_block(r, c, split_size);
+                self.decode_block(r, c + half, split_size);
+                self.decode_block(r + half, c, sub_size);
+            }
+            PARTITION_HORZ_B => {
+                self.decode_block(r, c, sub_size);
+                self.decode_block(r + half, c, split_size);
+                self.decode_block(r + half, c + half, split_size);
+            }
+            PARTITION_VERT_A => {
+                self.decode_block(r, c, split_size);
+                self.decode_block(r + half, c, split_size);
+                self.decode_block(r, c + half, sub_size);
+            }
+            PARTITION_VERT_B => {
+                self.decode_block(r, c, sub_size);
+                self.decode_block(r, c + half, split_size);
+                self.decode_block(r + half, c + half, split_size);
+            }
+            PARTITION_HORZ_4 => {
+                for i in 0..4 {
+                    if i < 3 || r + quarter * 3 < hdr.mi_rows {
+                        self.decode_block(r + quarter * i, c, sub_size);
+                    }
+                }
+            }
+            _ => {
+                for i in 0..4 {
+                    if i < 3 || c + quarter * 3 < hdr.mi_cols {
+                        self.decode_block(r, c + quarter * i, sub_size);
+                    }
+                }
+            }
+        }
+    }
+
+    fn decode_block(&mut self, r: usize, c: usize, sub_size: usize) {
+        let f = self.f;
+        let (bw4, bh4) = (block_width4(sub_size), block_height4(sub_size));
+        let color_map_y = std::mem::take(&mut self.b.color_map_y);
+        let color_map_uv = std::mem::take(&mut self.b.color_map_uv);
+        self.b = Block { mi_row: r, mi_col: c, mi_size: sub_size, bw4, bh4, color_map_y, color_map_uv, ..Default::default() };
+        let b = &mut self.b;
+        b.has_chroma = if (bh4 == 1 && f.ss_y && r & 1 == 0) || (bw4 == 1 && f.ss_x && c & 1 == 0) {
+            false
+        } else {
+            f.num_planes > 1
+        };
+        let (ri, ci) = (r as isize, c as isize);
+        let avail_u = self.is_inside(ri - 1, ci);
+        let avail_l = self.is_inside(ri, ci - 1);
+        let mut avail_u_chroma = avail_u;
+        let mut avail_l_chroma = avail_l;
+        if self.b.has_chroma {
+            if f.ss_y && bh4 == 1 {
+                avail_u_chroma = self.is_inside(ri - 2, ci);
+            }
+            if f.ss_x && bw4 == 1 {
+                avail_l_chroma = self.is_inside(ri, ci - 2);
+            }
+        } else {
+            avail_u_chroma = false;
+            avail_l_chroma = false;
+        }
+        let b = &mut self.b;
+        (b.avail_u, b.avail_l, b.avail_u_chroma, b.avail_l_chroma) = (avail_u, avail_l, avail_u_chroma, avail_l_chroma);
+
+        if f.hdr.frame_is_intra {
+            self.intra_frame_mode_info();
+        } else {
+            self.inter_frame_mode_info();
+        }
+        self.palette_tokens();
+        self.read_block_tx_size();
+        if self.b.skip {
+            self.reset_block_context();
+        }
+        self.store_mode_info();
+        self.compute_prediction();
+        self.residual();
+    }
+
+    fn store_mode_info(&mut self) {
+        let b = &self.b;
+        let palette = if b.palette_size_y > 0 || b.palette_size_uv > 0 {
+            self.palettes.push([b.palette_colors[0], b.palette_colors[1]]);
+            self.palettes.len() as u32 - 1
+        } else {
+            0
+        };
+        let b = &self.b;
+        let is_inter = b.is_inter;
+        let delta_lf = self.delta_lf.map(|d| d as i8);
+        let (rows, cols) = (b.bh4.min(self.f.hdr.mi_rows - b.mi_row), b.bw4.min(self.f.hdr.mi_cols - b.mi_col));
+        for row in b.mi_row..b.mi_row + rows {
+            for col in b.mi_col..b.mi_col + cols {
+                let mi = self.out.mi.get_mut(row, col);
+                let inter_tx_size = mi.inter_tx_size;
+                let tx_type = mi.tx_type;
+                *mi = MiInfo {
+                    mi_size: b.mi_size as u8,
+                    skip: b.skip,
+                    skip_mode: b.skip_mode,
+                    is_inter,
+                    y_mode: b.y_mode,
+                    uv_mode: b.uv_mode,
+                    ref_frame: b.ref_frame,
+                    mv: if is_inter { b.mv } else { [[0; 2]; 2] },
+                    segment_id: b.segment_id,
+                    inter_tx_size,
+                    tx_type,
+                    palette_size: [b.palette_size_y as u8, b.palette_size_uv as u8],
+                    palette,
+                    interp_filter: b.interp_filter,
+                    comp_group_idx: b.comp_group_idx,
+                    compound_idx: b.compound_idx,
+                    delta_lf,
+                };
+            }
+        }
+    }
+
+    fn reset_block_context(&mut self) {
+        let b = &self.b;
+        for plane in 0..1 + 2 * b.has_chroma as usize {
+            let (sx, sy) = self.f.sub(plane);
+            for i in b.mi_col >> sx..((b.mi_col + b.bw4 - 1) >> sx) + 1 {
+                self.above_level[plane][i] = 0;
+                self.above_dc[plane][i] = 0;
+            }
+            for i in b.mi_row >> sy..((b.mi_row + b.bh4 - 1) >> sy) + 1 {
+                self.left_level[plane][i] = 0;
+                self.left_dc[plane][i] = 0;
+            }
+        }
+    }
+
+    // Mode info
+
+    fn intra_frame_mode_info(&mut self) {
+        let hdr = self.f.hdr;
+        if hdr.seg_id_pre_skip {
+            self.intra_segment_id();
+        }
+        self.read_skip();
+        if !hdr.seg_id_pre_skip {
+            self.intra_segment_id();
+        }
+        self.read_cdef();
+        self.read_delta_qindex();
+        self.read_delta_lf();
+        self.read_deltas = false;
+        self.b.ref_frame = [INTRA_FRAME as i8, NONE_FRAME];
+        self.b.use_intrabc = hdr.allow_intrabc && self.sd.read_symbol(&mut self.cdf.intrabc) == 1;
+        if self.b.use_intrabc {
+            let b = &mut self.b;
+            b.is_inter = true;
+            b.motion_mode = SIMPLE;
+            b.compound_type = COMPOUND_AVERAGE;
+            b.interp_filter = [BILINEAR; 2];
+            self.find_mv_stack(false);
+            self.assign_mv(false);
+        } else {
+            self.b.is_inter = false;
+            let above = if self.b.avail_u { self.mi(self.b.mi_row - 1, self.b.mi_col).y_mode } else { DC_PRED };
+            let left = if self.b.avail_l { self.mi(self.b.mi_row, self.b.mi_col - 1).y_mode } else { DC_PRED };
+            let cdf = &mut self.cdf.kf_y_mode[INTRA_MODE_CONTEXT[above as usize]][INTRA_MODE_CONTEXT[left as usize]];
+            self.b.y_mode = self.sd.read_symbol(cdf) as u8;
+            self.intra_mode_info_rest();
+        }
+    }
+
+    /// Angle deltas, chroma mode, palette and filter intra of intra blocks
+    fn intra_mode_info_rest(&mut self) {
+        let b = &self.b;
+        let use_angle_delta = b.mi_size >= BLOCK_8X8;
+        if use_angle_delta && is_directional_mode(b.y_mode) {
+            let v = self.sd.read_symbol(&mut self.cdf.angle_delta[(self.b.y_mode - V_PRED) as usize]);
+            self.b.angle_delta_y = v as i32 - 3;
+        }
+        if self.b.has_chroma {
+            let b = &self.b;
+            let (bw, bh) = (block_width(b.mi_size), block_height(b.mi_size));
+            let cfl_allowed = if b.lossless {
+                subsampled_size(b.mi_size, self.f.ss_x, self.f.ss_y) == BLOCK_4X4
+            } else {
+                bw.max(bh) <= 32
+            };
+            let y_mode = b.y_mode as usize;
+            self.b.uv_mode = if cfl_allowed {
+                self.sd.read_symbol(&mut self.cdf.uv_mode_cfl[y_mode]) as u8
+            } else {
+                self.sd.read_symbol(&mut self.cdf.uv_mode[y_mode]) as u8
+            };
+            if self.b.uv_mode == UV_CFL_PRED {
+                self.read_cfl_alphas();
+            }
+            if use_angle_delta && is_directional_mode(self.b.uv_mode) {
+                let v = self.sd.read_symbol(&mut self.cdf.angle_delta[(self.b.uv_mode - V_PRED) as usize]);
+                self.b.angle_delta_uv = v as i32 - 3;
+            }
+        }
+        let b = &self.b;
+        let (bw, bh) = (block_width(b.mi_size), block_height(b.mi_size));
+        if b.mi_size >= BLOCK_8X8 && bw <= 64 && bh <= 64 && self.f.hdr.allow_screen_content_tools {
+            self.palette_mode_info();
+        }
+        let b = &self.b;
+        if self.f.seq.enable_filter_intra && b.y_mode == DC_PRED && b.palette_size_y == 0 && bw.max(bh) <= 32 {
+            self.b.use_filter_intra = self.sd.read_symbol(&mut self.cdf.filter_intra[self.b.mi_size]) == 1;
+            if self.b.use_filter_intra {
+                self.b.filter_intra_mode = self.sd.read_symbol(&mut self.cdf.filter_intra_mode) as u8;
+            }
+        }
+    }
+
+    fn read_cfl_alphas(&mut self) {
+        let signs = self.sd.read_symbol(&mut self.cdf.cfl_sign);
+        let sign_u = (signs + 1) / 3;
+        let sign_v = (signs + 1) % 3;
+        self.b.cfl_alpha_u = if sign_u != 0 {
+            let a = 1 + self.sd.read_symbol(&mut self.cdf.cfl_alpha[(sign_u - 1) * 3 + sign_v]) as i32;
+            if sign_u == 1 { -a } else { a }
+        } else {
+            0
+        };
+        self.b.cfl_alpha_v = if sign_v != 0 {
+            let a = 1 + self.sd.read_symbol(&mut self.cdf.cfl_alpha[(sign_v - 1) * 3 + sign_u]) as i32;
+            if sign_v == 1 { -a } else { a }
+        } else {
+            0
+        };
+    }
+
+    fn intra_segment_id(&mut self) {
+        if self.f.hdr.segmentation_enabled {
+            self.read_segment_id();
+        } else {
+            self.b.segment_id = 0;
+        }
+        self.b.lossless = self.f.hdr.lossless_array[self.b.segment_id as usize];
+    }
+
+    fn read_segment_id(&mut self) {
+        let b = &self.b;
+        let (r, c) = (b.mi_row, b.mi_col);
+        let prev_ul = if b.avail_u && b.avail_l { self.mi(r - 1, c - 1).segment_id as i32 } else { -1 };
+        let prev_u = if b.avail_u { self.mi(r - 1, c).segment_id as i32 } else { -1 };
+        let prev_l = if b.avail_l { self.mi(r, c - 1).segment_id as i32 } else { -1 };
+        let pred = if prev_u == -1 {
+            if prev_l == -1 { 0 } else { prev_l }
+        } else if prev_l == -1 || prev_ul == prev_u {
+            prev_u
+        } else {
+            prev_l
+        };
+        self.b.segment_id = if b.skip {
+            pred as u8
+        } else {
+            let ctx = if prev_ul < 0 {
+                0
+            } else if prev_ul == prev_u && prev_ul == prev_l {
+                2
+            } else if prev_ul == prev_u || prev_ul == prev_l || prev_u == prev_l {
+                1
+            } else {
+                0
+            };
+            let last = self.f.hdr.last_active_seg_id as i32;
+            let v = self.sd.read_symbol(&mut self.cdf.segment_id[ctx]) as i32;
+            neg_deinterleave(v, pred, last + 1).clamp(0, last) as u8
+        };
+    }
+
+    fn read_skip(&mut self) {
+        let b = &self.b;
+        if self.f.hdr.seg_id_pre_skip && self.f.seg_feature_active(b.segment_id, SEG_LVL_SKIP) {
+            self.b.skip = true;
+        } else {
+            let mut ctx = 0;
+            if b.avail_u {
+                ctx += self.mi(b.mi_row - 1, b.mi_col).skip as usize;
+            }
+            if b.avail_l {
+                ctx += self.mi(b.mi_row, b.mi_col - 1).skip as usize;
+            }
+            self.b.skip = self.sd.read_symbol(&mut self.cdf.skip[ctx]) == 1;
+        }
+    }
+
+    fn read_cdef(&mut self) {
+        let (hdr, seq) = (self.f.hdr, self.f.seq);
+        if self.b.skip || hdr.coded_lossless || !seq.enable_cdef || hdr.allow_intrabc {
+            return;
+        }
+        let (r, c) = (self.b.mi_row >> 4, self.b.mi_col >> 4);
+        if *self.out.cdef_idx.get(r, c) == -1 {
+            let idx = self.sd.read_literal(hdr.cdef_bits) as i8;
+            let (w4, h4) = (self.b.bw4, self.b.bh4);
+            // A 128 wide or high block codes one strength for all of its
+            // 64x64 blocks
+            self.out.cdef_idx.fill(r, c, h4.div_ceil(16), w4.div_ceil(16), idx);
+        }
+    }
+
+    fn read_delta_qindex(&mut self) {
+        let sb_size = self.f.sb_size();
+        if self.b.mi_size == sb_size && self.b.skip {
+            return;
+        }
+        if self.read_deltas {
+            let mut abs = self.sd.read_symbol(&mut self.cdf.delta_q) as i32;
+            if abs == DELTA_Q_SMALL as i32 {
+                let rem_bits = self.sd.read_literal(3) + 1;
+                abs = self.sd.read_literal(rem_bits) as i32 + (1 << rem_bits) + 1;
+            }
+            if abs != 0 {
+                let sign = self.sd.read_literal(1) == 1;
+                let reduced = if sign { -abs } else { abs };
+                self.current_q_index = (self.current_q_index + (reduced << self.f.hdr.delta_q_res)).clamp(1, 255);
+            }
+        }
+    }
+
+    fn read_delta_lf(&mut self) {
+        let hdr = self.f.hdr;
+        if self.b.mi_size == self.f.sb_size() && self.b.skip {
+            return;
+        }
+        if self.read_deltas && hdr.delta_lf_present {
+            let count = if hdr.delta_lf_multi { if self.f.num_planes > 1 { 4 } else { 2 } } else { 1 };
+            for i in 0..count {
+                let cdf: &mut [u16] = if hdr.delta_lf_multi { &mut self.cdf.delta_lf_multi[i] } else { &mut self.cdf.delta_lf };
+                let mut abs = self.sd.read_symbol(cdf) as i32;
+                if abs == DELTA_LF_SMALL as i32 {
+                    let n = self.sd.read_literal(3) + 1;
+                    abs = self.sd.read_literal(n) as i32 + (1 << n) + 1;
+                }
+                if abs != 0 {
+                    let sign = self.sd.read_literal(1) == 1;
+                    let reduced = if sign { -abs } else { abs };
+                    self.delta_lf[i] =
+                        (self.delta_lf[i] + (reduced << hdr.delta_lf_res)).clamp(-MAX_LOOP_FILTER, MAX_LOOP_FILTER);
+                }
+            }
+        }
+    }
+
+    fn palette_mode_info(&mut self) {
+        let bd = self.f.bit_depth as u32;
+        let b = &self.b;
+        let bsize_ctx = (BLOCK_WIDTH_LOG2[b.mi_size] + BLOCK_HEIGHT_LOG2[b.mi_size]) as usize - 6;
+        if b.y_mode == DC_PRED {
+            let mut ctx = 0;
+            if b.avail_u && self.mi(b.mi_row - 1, b.mi_col).palette_size[0] > 0 {
+                ctx += 1;
+            }
+            if b.avail_l && self.mi(b.mi_row, b.mi_col - 1).palette_size[0] > 0 {
+                ctx += 1;
+            }
+            if self.sd.read_symbol(&mut self.cdf.palette_y_mode[bsize_ctx][ctx]) == 1 {
+                let n = self.sd.read_symbol(&mut self.cdf.palette_y_size[bsize_ctx]) + 2;
+                self.b.palette_size_y = n;
+                let colors = self.read_palette_colors(0, n, bd);
+                self.b.palette_colors[0] = colors;
+            }
+        }
+        if self.b.has_chroma && self.b.uv_mode == DC_PRED {
+            let ctx = (self.b.palette_size_y > 0) as usize;
+            if self.sd.read_symbol(&mut self.cdf.palette_uv_mode[ctx]) == 1 {
+                let n = self.sd.read_symbol(&mut self.cdf.palette_uv_size[bsize_ctx]) + 2;
+                self.b.palette_size_uv = n;
+                self.b.palette_colors[1] = self.read_palette_colors(1, n, bd);
+                let mut v = [0u16; 8];
+                if self.sd.read_literal(1) == 1 {
+                    let min_bits = bd - 4;
+                    let max_val = 1i32 << bd;
+                    let bits = min_bits + self.sd.read_literal(2);
+                    v[0] = self.sd.read_literal(bd) as u16;
+                    for idx in 1..n {
+                        let mut delta = self.sd.read_literal(bits) as i32;
+                        if delta != 0 && self.sd.read_literal(1) == 1 {
+                            delta = -delta;
+                        }
+                        let mut val = v[idx - 1] as i32 + delta;
+                        if val < 0 {
+                            val += max_val;
+                        }
+                        if val >= max_val {
+                            val -= max_val;
+                        }
+                        v[idx] = val.clamp(0, max_val - 1) as u16;
+                    }
+                } else {
+                    for c in v.iter_mut().take(n) {
+                        *c = self.sd.read_literal(bd) as u16;
+                    }
+                }
+                self.b.palette_colors[2] = v;
+            }
+        }
+    }
+
+    /// Luma or U palette: colors from the neighbour cache, then literals
+    /// and ascending deltas
+    fn read_palette_colors(&mut self, plane: usize, n: usize, bd: u32) -> [u16; 8] {
+        let cache = self.palette_cache(plane);
+        let mut colors = [0u16; 8];
+        let mut idx = 0;
+        for &c in &cache {
+            if idx >= n {
+                break;
+            }
+            if self.sd.read_literal(1) == 1 {
+                colors[idx] = c;
+                idx += 1;
+            }
+        }
+        if idx < n {
+            colors[idx] = self.sd.read_literal(bd) as u16;
+            idx += 1;
+        }
+        let mut bits = 0;
+        if idx < n {
+            bits = bd - 3 + self.sd.read_literal(2);
+        }
+        let max = (1u32 << bd) - 1;
+        while idx < n {
+            let mut delta = self.sd.read_literal(bits);
+            if plane == 0 {
+                delta += 1;
+            }
+            colors[idx] = (colors[idx - 1] as u32 + delta).min(max) as u16;
+            let range = (1u32 << bd) - colors[idx] as u32 - (plane == 0) as u32;
+            bits = bits.min(ceil_log2(range));
+            idx += 1;
+        }
+        colors[..n].sort_unstable();
+        colors
+    }
+
+    fn palette_cache(&self, plane: usize) -> Vec<u16> {
+        let b = &self.b;
+        let above = if !(b.mi_row * 4).is_multiple_of(64) && b.avail_u {
+            let mi = self.mi(b.mi_row - 1, b.mi_col);
+            &self.palettes[mi.palette as usize][plane][..mi.palette_size[plane] as usize]
+        } else {
+            &[]
+        };
+        let left = if b.avail_l {
+            let mi = self.mi(b.mi_row, b.mi_col - 1);
+            &self.palettes[mi.palette as usize][plane][..mi.palette_size[plane] as usize]
+        } else {
+            &[]
+        };
+        let mut merged: Vec<u16> = above.iter().chain(left).copied().collect();
+        merged.sort_unstable();
+        merged.dedup();
+        merged
+    }
+
+    fn palette_tokens(&mut self) {
+        let b = &self.b;
+        let hdr = self.f.hdr;
+        let (bw, bh) = (block_width(b.mi_size), block_height(b.mi_size));
+        let onscreen_h = bh.min((hdr.mi_rows - b.mi_row) * 4);
+        let onscreen_w = bw.min((hdr.mi_cols - b.mi_col) * 4);
+        if b.palette_size_y > 0 {
+            let n = b.palette_size_y;
+            let mut map = std::mem::take(&mut self.b.color_map_y);
+            self.read_color_map(&mut map, 0, n, bw, bh, onscreen_w, onscreen_h);
+            self.b.color_map_y = map;
+        }
+        let b = &self.b;
+        if b.palette_size_uv > 0 {
+            let n = b.palette_size_uv;
+            let (sx, sy) = (self.f.ss_x as usize, self.f.ss_y as usize);
+            let (mut bw, mut bh, mut ow, mut oh) = (bw >> sx, bh >> sy, onscreen_w >> sx, onscreen_h >> sy);
+            if bw < 4 {
+                bw += 2;
+                ow += 2;
+            }
+            if bh < 4 {
+                bh += 2;
+                oh += 2;
+            }
+            let mut map = std::mem::take(&mut self.b.color_map_uv);
+            self.read_color_map(&mut map, 1, n, bw, bh, ow, oh);
+            self.b.color_map_uv = map;
+        }
+    }
+
+    /// Color indices in anti-diagonal order, each coded relative to the
+    /// order of its neighbours' colors
+    #[allow(clippy::too_many_arguments)]
+    fn read_color_map(&mut self, map: &mut Vec<u8>, plane: usize, n: usize, bw: usize, bh: usize, ow: usize, oh: usize) {
+        map.clear();
+        map.resize(bw * bh, 0);
+        map[0] = self.sd.read_ns(n as u32) as u8;
+        for i in 1..oh + ow - 1 {
+            for j in (i.saturating_sub(oh - 1)..=i.min(ow - 1)).rev() {
+                let (r, c) = (i - j, j);
+                let mut scores = [0u32; 8];
+                let mut order = [0u8, 1, 2, 3, 4, 5, 6, 7];
+                if c > 0 {
+                    scores[map[r * bw + c - 1] as usize] += 2;
+                }
+                if r > 0 && c > 0 {
+                    scores[map[(r - 1) * bw + c - 1] as usize] += 1;
+                }
+                if r > 0 {
+                    scores[map[(r - 1) * bw + c] as usize] += 2;
+                }
+                for k in 0..3 {
+                    let mut max_score = scores[k];
+                    let mut max_idx = k;
+                    for (l, &s) in scores.iter().enumerate().take(n).skip(k + 1) {
+                        if s > max_score {
+                            max_score = s;
+                            max_idx = l;
+                        }
+                    }
+                    if max_idx != k {
+                        let max_order = order[max_idx];
+                        for l in (k + 1..=max_idx).rev() {
+                            scores[l] = scores[l - 1];
+                            order[l] = order[l - 1];
+                        }
+                        scores[k] = max_score;
+                        order[k] = max_order;
+                    }
+                }
+                let hash: u32 = (0..3).map(|k| scores[k] * PALETTE_COLOR_HASH_MULTIPLIERS[k]).sum();
+                let ctx = PALETTE_COLOR_CONTEXT[hash as usize] as usize;
+                let cdf: &mut [u16] = if plane == 0 {
+                    &mut self.cdf.palette_y_color[n - 2][ctx][..n + 1]
+                } else {
+                    &mut self.cdf.palette_uv_color[n - 2][ctx][..n + 1]
+                };
+                let idx = self.sd.read_symbol(cdf);
+                map[r * bw + c] = order[idx];
+            }
+        }
+        for i in 0..oh {
+            for j in ow..bw {
+                map[i * bw + j] = map[i * bw + ow - 1];
+            }
+        }
+        for i in oh..bh {
+            for j in 0..bw {
+                map[i * bw + j] = map[(oh - 1) * bw + j];
+            }
+        }
+    }
+
+    fn inter_frame_mode_info(&mut self) {
+        let hdr = self.f.hdr;
+        let b = &self.b;
+        let (r, c) = (b.mi_row, b.mi_col);
+        let left = if b.avail_l { self.mi(r, c - 1).ref_frame } else { [INTRA_FRAME as i8, NONE_FRAME] };
+        let above = if b.avail_u { self.mi(r - 1, c).ref_frame } else { [INTRA_FRAME as i8, NONE_FRAME] };
+        let b = &mut self.b;
+        b.left_ref = left;
+        b.above_ref = above;
+        b.left_intra = left[0] <= INTRA_FRAME as i8;
+        b.above_intra = above[0] <= INTRA_FRAME as i8;
+        b.left_single = left[1] <= INTRA_FRAME as i8;
+        b.above_single = above[1] <= INTRA_FRAME as i8;
+        self.inter_segment_id(true);
+        self.read_skip_mode();
+        if self.b.skip_mode {
+            self.b.skip = true;
+        } else {
+            self.read_skip();
+        }
+        if !hdr.seg_id_pre_skip {
+            self.inter_segment_id(false);
+        }
+        self.b.lossless = hdr.lossless_array[self.b.segment_id as usize];
+        self.read_cdef();
+        self.read_delta_qindex();
+        self.read_delta_lf();
+        self.read_deltas = false;
+        self.read_is_inter();
+        if self.b.is_inter {
+            self.inter_block_mode_info();
+        } else {
+            self.b.ref_frame = [INTRA_FRAME as i8, NONE_FRAME];
+            let cdf = &mut self.cdf.y_mode[SIZE_GROUP[self.b.mi_size] as usize];
+            self.b.y_mode = self.sd.read_symbol(cdf) as u8;
+            self.intra_mode_info_rest();
+        }
+    }
+
+    fn inter_segment_id(&mut self, pre_skip: bool) {
+        let hdr = self.f.hdr;
+        if !hdr.segmentation_enabled {
+            self.b.segment_id = 0;
+            return;
+        }
+        let predicted = self.predicted_segment_id();
+        if !hdr.segmentation_update_map {
+            self.b.segment_id = predicted;
+            return;
+        }
+        if pre_skip && !hdr.seg_id_pre_skip {
+            self.b.segment_id = 0;
+            return;
+        }
+        let b = &self.b;
+        let (r, c, bw4, bh4) = (b.mi_row, b.mi_col, b.bw4, b.bh4);
+        if !pre_skip && b.skip {
+            self.above_seg_pred[c..c + bw4].fill(false);
+            self.left_seg_pred[r..r + bh4].fill(false);
+            self.read_segment_id();
+            return;
+        }
+        if hdr.segmentation_temporal_update {
+            let ctx = self.left_seg_pred[r] as usize + self.above_seg_pred[c] as usize;
+            let predicted_flag = self.sd.read_symbol(&mut self.cdf.segment_id_predicted[ctx]) == 1;
+            if predicted_flag {
+                self.b.segment_id = predicted;
+            } else {
+                self.read_segment_id();
+            }
+            self.above_seg_pred[c..c + bw4].fill(predicted_flag);
+            self.left_seg_pred[r..r + bh4].fill(predicted_flag);
+        } else {
+            self.read_segment_id();
+        }
+    }
+
+    /// Smallest segment of the co-located area of the previous map
+    fn predicted_segment_id(&self) -> u8 {
+        let Some(prev) = self.f.prev_segment_ids else { return 0 };
+        let b = &self.b;
+        let x_mis = (self.f.hdr.mi_cols - b.mi_col).min(b.bw4);
+        let y_mis = (self.f.hdr.mi_rows - b.mi_row).min(b.bh4);
+        let mut seg = 7;
+        for y in 0..y_mis {
+            for x in 0..x_mis {
+                seg = seg.min(*prev.get(b.mi_row + y, b.mi_col + x));
+            }
+        }
+        seg
+    }
+
+    fn read_skip_mode(&mut self) {
+        let (f, b) = (self.f, &self.b);
+        let seg = b.segment_id;
+        if f.seg_feature_active(seg, SEG_LVL_SKIP)
+            || f.seg_feature_active(seg, SEG_LVL_REF_FRAME)
+            || f.seg_feature_active(seg, SEG_LVL_GLOBALMV)
+            || !f.hdr.skip_mode_present
+            || block_width(b.mi_size) < 8
+            || block_height(b.mi_size) < 8
+        {
+            self.b.skip_mode = false;
+            return;
+        }
+        let mut ctx = 0;
+        if b.avail_u {
+            ctx += self.mi(b.mi_row - 1, b.mi_col).skip_mode as usize;
+        }
+        if b.avail_l {
+            ctx += self.mi(b.mi_row, b.mi_col - 1).skip_mode as usize;
+        }
+        self.b.skip_mode = self.sd.read_symbol(&mut self.cdf.skip_mode[ctx]) == 1;
+    }
+
+    fn read_is_inter(&mut self) {
+        let (f, b) = (self.f, &self.b);
+        self.b.is_inter = if b.skip_mode {
+            true
+        } else if f.seg_feature_active(b.segment_id, SEG_LVL_REF_FRAME) {
+            f.hdr.feature_data[b.segment_id as usize][SEG_LVL_REF_FRAME] != INTRA_FRAME as i32
+        } else if f.seg_feature_active(b.segment_id, SEG_LVL_GLOBALMV) {
+            true
+        } else {
+            let ctx = if b.avail_u && b.avail_l {
+                if b.left_intra && b.above_intra { 3 } else { (b.left_intra || b.above_intra) as usize }
+            } else if b.avail_u || b.avail_l {
+                2 * (if b.avail_u { b.above_intra } else { b.left_intra }) as usize
+            } else {
+                0
+            };
+            self.sd.read_symbol(&mut self.cdf.is_inter[ctx]) == 1
+        };
+    }
+
+    fn inter_block_mode_info(&mut self) {
+        let (f, hdr) = (self.f, self.f.hdr);
+        self.read_ref_frames();
+        let is_compound = self.b.ref_frame[1] > INTRA_FRAME as i8;
+        self.find_mv_stack(is_compound);
+        let seg = self.b.segment_id;
+        let b = &mut self.b;
+        if b.skip_mode {
+            b.y_mode = NEAREST_NEARESTMV;
+        } else if f.seg_feature_active(seg, SEG_LVL_SKIP) || f.seg_feature_active(seg, SEG_LVL_GLOBALMV) {
+            b.y_mode = GLOBALMV;
+        } else if is_compound {
+            let ctx = COMPOUND_MODE_CTX_MAP[b.ref_mv_context >> 1][b.new_mv_context.min(4)];
+            b.y_mode = NEAREST_NEARESTMV + self.sd.read_symbol(&mut self.cdf.compound_mode[ctx]) as u8;
+        } else if self.sd.read_symbol(&mut self.cdf.new_mv[b.new_mv_context]) == 0 {
+            b.y_mode = NEWMV;
+        } else if self.sd.read_symbol(&mut self.cdf.zero_mv[b.zero_mv_context]) == 0 {
+            b.y_mode = GLOBALMV;
+        } else {
+            b.y_mode = if self.sd.read_symbol(&mut self.cdf.ref_mv[b.ref_mv_context]) == 0 { NEARESTMV } else { NEARMV };
+        }
+        let y_mode = self.b.y_mode;
+        self.b.ref_mv_idx = 0;
+        let has_nearmv = matches!(y_mode, NEARMV | NEAR_NEARMV | NEAR_NEWMV | NEW_NEARMV);
+        if y_mode == NEWMV || y_mode == NEW_NEWMV || has_nearmv {
+            let start = has_nearmv as usize;
+            self.b.ref_mv_idx = start;
+            for idx in start..start + 2 {
+                if self.b.num_mv_found > idx + 1 {
+                    let ctx = self.b.drl_ctx_stack[idx];
+                    if self.sd.read_symbol(&mut self.cdf.drl_mode[ctx]) == 0 {
+                        self.b.ref_mv_idx = idx;
+                        break;
+                    }
+                    self.b.ref_mv_idx = idx + 1;
+                }
+            }
+        }
+        self.assign_mv(is_compound);
+        self.read_interintra_mode(is_compound);
+        self.read_motion_mode(is_compound);
+        self.read_compound_type(is_compound);
+        if hdr.interpolation_filter == SWITCHABLE {
+            let dirs = if f.seq.enable_dual_filter { 2 } else { 1 };
+            for dir in 0..dirs {
+                self.b.interp_filter[dir] = if self.needs_interp_filter() {
+                    let ctx = self.interp_filter_ctx(dir);
+                    self.sd.read_symbol(&mut self.cdf.interp_filter[ctx]) as u8
+                } else {
+                    EIGHTTAP
+                };
+            }
+            if !f.seq.enable_dual_filter {
+                self.b.interp_filter[1] = self.b.interp_filter[0];
+            }
+        } else {
+            self.b.interp_filter = [hdr.interpolation_filter; 2];
+        }
+    }
+
+    fn needs_interp_filter(&self) -> bool {
+        let b = &self.b;
+        let large = block_width(b.mi_size).min(block_height(b.mi_size)) >= 8;
+        let gm = &self.f.hdr.gm_type;
+        if b.skip_mode || b.motion_mode == LOCALWARP {
+            false
+        } else if large && b.y_mode == GLOBALMV {
+            gm[b.ref_frame[0] as usize] == TRANSLATION
+        } else if large && b.y_mode == GLOBAL_GLOBALMV {
+            gm[b.ref_frame[0] as usize] == TRANSLATION || gm[b.ref_frame[1] as usize] == TRANSLATION
+        } else {
+            true
+        }
+    }
+
+    fn interp_filter_ctx(&self, dir: usize) -> usize {
+        let b = &self.b;
+        let mut ctx = ((dir & 1) * 2 + (b.ref_frame[1] > INTRA_FRAME as i8) as usize) * 4;
+        let mut left_type = 3;
+        let mut above_type = 3;
+        if b.avail_l {
+            let mi = self.mi(b.mi_row, b.mi_col - 1);
+            if mi.ref_frame[0] == b.ref_frame[0] || mi.ref_frame[1] == b.ref_frame[0] {
+                left_type = mi.interp_filter[dir] as usize;
+            }
+        }
+        if b.avail_u {
+            let mi = self.mi(b.mi_row - 1, b.mi_col);
+            if mi.ref_frame[0] == b.ref_frame[0] || mi.ref_frame[1] == b.ref_frame[0] {
+                above_type = mi.interp_filter[dir] as usize;
+            }
+        }
+        ctx += if left_type == above_type {
+            left_type
+        } else if left_type == 3 {
+            above_type
+        } else if above_type == 3 {
+            left_type
+        } else {
+            3
+        };
+        ctx
+    }
+
+    fn count_refs(&self, frame: usize) -> usize {
+        let b = &self.b;
+        let frame = frame as i8;
+        let mut c = 0;
+        if b.avail_u {
+            c += (b.above_ref[0] == frame) as usize + (b.above_ref[1] == frame) as usize;
+        }
+        if b.avail_l {
+            c += (b.left_ref[0] == frame) as usize + (b.left_ref[1] == frame) as usize;
+        }
+        c
+    }
+
+    /// Context comparing neighbour counts of two groups of references
+    fn ref_count_ctx(&self, a: &[usize], b: &[usize]) -> usize {
+        let ca: usize = a.iter().map(|&f| self.count_refs(f)).sum();
+        let cb: usize = b.iter().map(|&f| self.count_refs(f)).sum();
+        match ca.cmp(&cb) {
+            std::cmp::Ordering::Less => 0,
+            std::cmp::Ordering::Equal => 1,
+            std::cmp::Ordering::Greater => 2,
+        }
+    }
+
+    fn read_ref_frames(&mut self) {
+        let (f, hdr) = (self.f, self.f.hdr);
+        let seg = self.b.segment_id;
+        if self.b.skip_mode {
+            self.b.ref_frame = [hdr.skip_mode_frame[0] as i8, hdr.skip_mode_frame[1] as i8];
+            return;
+        }
+        if f.seg_feature_active(seg, SEG_LVL_REF_FRAME) {
+            self.b.ref_frame = [hdr.feature_data[seg as usize][SEG_LVL_REF_FRAME] as i8, NONE_FRAME];
+            return;
+        }
+        if f.seg_feature_active(seg, SEG_LVL_SKIP) || f.seg_feature_active(seg, SEG_LVL_GLOBALMV) {
+            self.b.ref_frame = [LAST_FRAME as i8, NONE_FRAME];
+            return;
+        }
+        const FWD: [usize; 4] = [LAST_FRAME, LAST2_FRAME, LAST3_FRAME, GOLDEN_FRAME];
+        const BWD: [usize; 3] = [BWDREF_FRAME, ALTREF2_FRAME, ALTREF_FRAME];
+        let compound = hdr.reference_select && self.b.bw4.min(self.b.bh4) >= 2 && {
+            let ctx = self.comp_mode_ctx();
+            self.sd.read_symbol(&mut self.cdf.comp_mode[ctx]) == 1
+        };
+        let refs = if compound {
+            let ctx = self.comp_ref_type_ctx();
+            let bidir = self.sd.read_symbol(&mut self.cdf.comp_ref_type[ctx]) == 1;
+            if !bidir {
+                let ctx = self.ref_count_ctx(&FWD, &BWD);
+                if self.sd.read_symbol(&mut self.cdf.uni_comp_ref[ctx][0]) == 1 {
+                    [BWDREF_FRAME, ALTREF_FRAME]
+                } else {
+                    let ctx1 = self.ref_count_ctx(&[LAST2_FRAME], &[LAST3_FRAME, GOLDEN_FRAME]);
+                    if self.sd.read_symbol(&mut self.cdf.uni_comp_ref[ctx1][1]) == 1 {
+                        let ctx2 = self.ref_count_ctx(&[LAST3_FRAME], &[GOLDEN_FRAME]);
+                        if self.sd.read_symbol(&mut self.cdf.uni_comp_ref[ctx2][2]) == 1 {
+                            [LAST_FRAME, GOLDEN_FRAME]
+                        } else {
+                            [LAST_FRAME, LAST3_FRAME]
+                        }
+                    } else {
+                        [LAST_FRAME, LAST2_FRAME]
+                    }
+                }
+            } else {
+                let ctx = self.ref_count_ctx(&[LAST_FRAME, LAST2_FRAME], &[LAST3_FRAME, GOLDEN_FRAME]);
+                let first = if self.sd.read_symbol(&mut self.cdf.comp_ref[ctx][0]) == 0 {
+                    let ctx = self.ref_count_ctx(&[LAST_FRAME], &[LAST2_FRAME]);
+                    if self.sd.read_symbol(&mut self.cdf.comp_ref[ctx][1]) == 1 { LAST2_FRAME } else { LAST_FRAME }
+                } else {
+                    let ctx = self.ref_count_ctx(&[LAST3_FRAME], &[GOLDEN_FRAME]);
+                    if self.sd.read_symbol(&mut self.cdf.comp_ref[ctx][2]) == 1 { GOLDEN_FRAME } else { LAST3_FRAME }
+                };
+                let ctx = self.ref_count_ctx(&[BWDREF_FRAME, ALTREF2_FRAME], &[ALTREF_FRAME]);
+                let second = if self.sd.read_symbol(&mut self.cdf.comp_bwd_ref[ctx][0]) == 0 {
+                    let ctx = self.ref_count_ctx(&[BWDREF_FRAME], &[ALTREF2_FRAME]);
+                    if self.sd.read_symbol(&mut self.cdf.comp_bwd_ref[ctx][1]) == 1 { ALTREF2_FRAME } else { BWDREF_FRAME }
+                } else {
+                    ALTREF_FRAME
+                };
+                [first, second]
+            }
+        } else {
+            let ctx = self.ref_count_ctx(&FWD, &BWD);
+            let single = if self.sd.read_symbol(&mut self.cdf.single_ref[ctx][0]) == 1 {
+                let ctx = self.ref_count_ctx(&[BWDREF_FRAME, ALTREF2_FRAME], &[ALTREF_FRAME]);
+                if self.sd.read_symbol(&mut self.cdf.single_ref[ctx][1]) == 0 {
+                    let ctx = self.ref_count_ctx(&[BWDREF_FRAME], &[ALTREF2_FRAME]);
+                    if self.sd.read_symbol(&mut self.cdf.single_ref[ctx][5]) == 1 { ALTREF2_FRAME } else { BWDREF_FRAME }
+                } else {
+                    ALTREF_FRAME
+                }
+            } else {
+                let ctx = self.ref_count_ctx(&[LAST_FRAME, LAST2_FRAME], &[LAST3_FRAME, GOLDEN_FRAME]);
+                if self.sd.read_symbol(&mut self.cdf.single_ref[ctx][2]) == 1 {
+                    let ctx = self.ref_count_ctx(&[LAST3_FRAME], &[GOLDEN_FRAME]);
+                    if self.sd.read_symbol(&mut self.cdf.single_ref[ctx][4]) == 1 { GOLDEN_FRAME } else { LAST3_FRAME }
+                } else {
+                    let ctx = self.ref_count_ctx(&[LAST_FRAME], &[LAST2_FRAME]);
+                    if self.sd.read_symbol(&mut self.cdf.single_ref[ctx][3]) == 1 { LAST2_FRAME } else { LAST_FRAME }
+                }
+            };
+            self.b.ref_frame = [single as i8, NONE_FRAME];
+            return;
+        };
+        self.b.ref_frame = [refs[0] as i8, refs[1] as i8];
+    }
+
+    fn comp_mode_ctx(&self) -> usize {
+        let b = &self.b;
+        let backward = |r: i8| r >= BWDREF_FRAME as i8 && r <= ALTREF_FRAME as i8;
+        if b.avail_u && b.avail_l {
+            if b.above_single && b.left_single {
+                (backward(b.above_ref[0]) ^ backward(b.left_ref[0])) as usize
+            } else if b.above_single {
+                2 + (backward(b.above_ref[0]) || b.above_intra) as usize
+            } else if b.left_single {
+                2 + (backward(b.left_ref[0]) || b.left_intra) as usize
+            } else {
+                4
+            }
+        } else if b.avail_u {
+            if b.above_single { backward(b.above_ref[0]) as usize } else { 3 }
+        } else if b.avail_l {
+            if b.left_single { backward(b.left_ref[0]) as usize } else { 3 }
+        } else {
+            1
+        }
+    }
+
+    fn comp_ref_type_ctx(&self) -> usize {
+        let b = &self.b;
+        let bwd = |r: i8| r >= BWDREF_FRAME as i8;
+        let uni = |refs: [i8; 2]| bwd(refs[0]) == bwd(refs[1]);
+        let (a0, l0) = (b.above_ref[0], b.left_ref[0]);
+        if b.avail_u && b.avail_l {
+            if b.above_intra && b.left_intra {
+                2
+            } else if b.above_intra || b.left_intra {
+                let (single, refs) = if b.above_intra { (b.left_single, b.left_ref) } else { (b.above_single, b.above_ref) };
+                if single { 2 } else { 1 + 2 * uni(refs) as usize }
+            } else if b.above_single && b.left_single {
+                1 + 2 * (bwd(a0) == bwd(l0)) as usize
+            } else if b.above_single || b.left_single {
+                let comp_refs = if b.above_single { b.left_ref } else { b.above_ref };
+                if !uni(comp_refs) { 1 } else { 3 + (bwd(a0) == bwd(l0)) as usize }
+            } else {
+                let (au, lu) = (uni(b.above_ref), uni(b.left_ref));
+                if !au && !lu {
+                    0
+                } else if !au || !lu {
+                    2
+                } else {
+                    3 + ((a0 == BWDREF_FRAME as i8) == (l0 == BWDREF_FRAME as i8)) as usize
+                }
+            }
+        } else if b.avail_u || b.avail_l {
+            let (intra, single, refs) = if b.avail_u {
+                (b.above_intra, b.above_single, b.above_ref)
+            } else {
+                (b.left_intra, b.left_single, b.left_ref)
+            };
+            if intra || single { 2 } else { 4 * uni(refs) as usize }
+        } else {
+            2
+        }
+    }
+
+    fn read_interintra_mode(&mut self, is_compound: bool) {
+        let b = &self.b;
+        if !b.skip_mode
+            && self.f.seq.enable_interintra_compound
+            && !is_compound
+            && b.mi_size >= BLOCK_8X8
+            && b.mi_size <= BLOCK_32X32
+        {
+            let group = SIZE_GROUP[b.mi_size] as usize;
+            self.b.interintra = self.sd.read_symbol(&mut self.cdf.interintra[group]) == 1;
+            if self.b.interintra {
+                self.b.interintra_mode = self.sd.read_symbol(&mut self.cdf.interintra_mode[group]) as u8;
+                self.b.ref_frame[1] = INTRA_FRAME as i8;
+                self.b.angle_delta_y = 0;
+                self.b.angle_delta_uv = 0;
+                self.b.use_filter_intra = false;
+                let mi_size = self.b.mi_size;
+                self.b.wedge_interintra = self.sd.read_symbol(&mut self.cdf.wedge_interintra[mi_size]) == 1;
+                if self.b.wedge_interintra {
+                    self.b.wedge_index = self.sd.read_symbol(&mut self.cdf.wedge_index[mi_size]);
+                    self.b.wedge_sign = false;
+                }
+            }
+        }
+    }
+
+    fn read_motion_mode(&mut self, is_compound: bool) {
+        let hdr = self.f.hdr;
+        self.b.motion_mode = SIMPLE;
+        let b = &self.b;
+        if b.skip_mode || !hdr.is_motion_mode_switchable {
+            return;
+        }
+        if block_width(b.mi_size).min(block_height(b.mi_size)) < 8 {
+            return;
+        }
+        if !hdr.force_integer_mv
+            && (b.y_mode == GLOBALMV || b.y_mode == GLOBAL_GLOBALMV)
+            && hdr.gm_type[b.ref_frame[0] as usize] > TRANSLATION
+        {
+            return;
+        }
+        if is_compound || b.ref_frame[1] == INTRA_FRAME as i8 || !self.has_overlappable_candidates() {
+            return;
+        }
+        self.find_warp_samples();
+        let b = &self.b;
+        if hdr.force_integer_mv || b.num_samples == 0 || !hdr.allow_warped_motion || self.is_scaled(b.ref_frame[0]) {
+            let mi_size = b.mi_size;
+            if self.sd.read_symbol(&mut self.cdf.use_obmc[mi_size]) == 1 {
+                self.b.motion_mode = OBMC;
+            }
+        } else {
+            let mi_size = b.mi_size;
+            self.b.motion_mode = self.sd.read_symbol(&mut self.cdf.motion_mode[mi_size]) as u8;
+        }
+    }
+
+    fn has_overlappable_candidates(&self) -> bool {
+        let (b, hdr) = (&self.b, self.f.hdr);
+        if b.avail_u {
+            for x4 in (b.mi_col..hdr.mi_cols.min(b.mi_col + b.bw4)).step_by(2) {
+                let x5 = (x4 | 1).min(hdr.mi_cols - 1);
+                if self.mi(b.mi_row - 1, x5).ref_frame[0] > INTRA_FRAME as i8 {
+                    return true;
+                }
+            }
+        }
+        if b.avail_l {
+            for y4 in (b.mi_row..hdr.mi_rows.min(b.mi_row + b.bh4)).step_by(2) {
+                let y5 = (y4 | 1).min(hdr.mi_rows - 1);
+                if self.mi(y5, b.mi_col - 1).ref_frame[0] > INTRA_FRAME as i8 {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn read_compound_type(&mut self, is_compound: bool) {
+        let seq = self.f.seq;
+        self.b.comp_group_idx = 0;
+        self.b.compound_idx = 1;
+        if self.b.skip_mode {
+            self.b.compound_type = COMPOUND_AVERAGE;
+            return;
+        }
+        if is_compound {
+            let mi_size = self.b.mi_size;
+            let n = WEDGE_BITS[mi_size];
+            if seq.enable_masked_compound {
+                let ctx = self.comp_group_idx_ctx();
+                self.b.comp_group_idx = self.sd.read_symbol(&mut self.cdf.comp_group_idx[ctx]) as u8;
+            }
+            if self.b.comp_group_idx == 0 {
+                if seq.enable_jnt_comp {
+                    let ctx = self.compound_idx_ctx();
+                    self.b.compound_idx = self.sd.read_symbol(&mut self.cdf.compound_idx[ctx]) as u8;
+                    self.b.compound_type = if self.b.compound_idx == 1 { COMPOUND_AVERAGE } else { COMPOUND_DISTANCE };
+                } else {
+                    self.b.compound_type = COMPOUND_AVERAGE;
+                }
+            } else if n == 0 {
+                self.b.compound_type = COMPOUND_DIFFWTD;
+            } else {
+                self.b.compound_type = self.sd.read_symbol(&mut self.cdf.compound_type[mi_size]) as u8;
+            }
+            if self.b.compound_type == COMPOUND_WEDGE {
+                self.b.wedge_index = self.sd.read_symbol(&mut self.cdf.wedge_index[mi_size]);
+                self.b.wedge_sign = self.sd.read_literal(1) == 1;
+            } else if self.b.compound_type == COMPOUND_DIFFWTD {
+                self.b.mask_type = self.sd.read_literal(1) == 1;
+            }
+        } else if self.b.interintra {
+            self.b.compound_type = if self.b.wedge_interintra { COMPOUND_WEDGE } else { COMPOUND_INTRA };
+        } else {
+            self.b.compound_type = COMPOUND_AVERAGE;
+        }
+    }
+
+    fn comp_group_idx_ctx(&self) -> usize {
+        let b = &self.b;
+        let mut ctx = 0;
+        if b.avail_u {
+            if !b.above_single {
+                ctx += self.mi(b.mi_row - 1, b.mi_col).comp_group_idx as usize;
+            } else if b.above_ref[0] == ALTREF_FRAME as i8 {
+                ctx += 3;
+            }
+        }
+        if b.avail_l {
+            if !b.left_single {
+                ctx += self.mi(b.mi_row, b.mi_col - 1).comp_group_idx as usize;
+            } else if b.left_ref[0] == ALTREF_FRAME as i8 {
+                ctx += 3;
+            }
+        }
+        ctx.min(5)
+    }
+
+    fn compound_idx_ctx(&self) -> usize {
+        let (b, hdr, seq) = (&self.b, self.f.hdr, self.f.seq);
+        let fwd = FrameHeader::relative_dist(seq, hdr.order_hints[b.ref_frame[0] as usize], hdr.order_hint).abs();
+        let bck = FrameHeader::relative_dist(seq, hdr.order_hints[b.ref_frame[1] as usize], hdr.order_hint).abs();
+        let mut ctx = if fwd == bck { 3 } else { 0 };
+        if b.avail_u {
+            if !b.above_single {
+                ctx += self.mi(b.mi_row - 1, b.mi_col).compound_idx as usize;
+            } else if b.above_ref[0] == ALTREF_FRAME as i8 {
+                ctx += 1;
+            }
+        }
+        if b.avail_l {
+            if !b.left_single {
+                ctx += self.mi(b.mi_row, b.mi_col - 1).compound_idx as usize;
+            } else if b.left_ref[0] == ALTREF_FRAME as i8 {
+                ctx += 1;
+            }
+        }
+        ctx
+    }
+
+    // Transform sizes
+
+    fn read_block_tx_size(&mut self) {
+        let hdr = self.f.hdr;
+        let b = &self.b;
+        let (r, c, bw4, bh4) = (b.mi_row, b.mi_col, b.bw4, b.bh4);
+        if hdr.tx_mode == TX_MODE_SELECT && b.mi_size > BLOCK_4X4 && b.is_inter && !b.skip && !b.lossless {
+            let max_tx = MAX_TX_SIZE_RECT[b.mi_size];
+            let (tw4, th4) = (tx_width(max_tx) >> 2, tx_height(max_tx) >> 2);
+            for row in (r..r + bh4).step_by(th4) {
+                for col in (c..c + bw4).step_by(tw4) {
+                    self.read_var_tx_size(row, col, max_tx, 0);
+                }
+            }
+        } else {
+            self.read_tx_size(!b.skip || !b.is_inter);
+            let tx = self.b.tx_size as u8;
+            for row in r..(r + bh4).min(hdr.mi_rows) {
+                for col in c..(c + bw4).min(hdr.mi_cols) {
+                    self.out.mi.get_mut(row, col).inter_tx_size = tx;
+                }
+            }
+        }
+    }
+
+    fn read_var_tx_size(&mut self, row: usize, col: usize, tx: usize, depth: u32) {
+        let hdr = self.f.hdr;
+        if row >= hdr.mi_rows || col >= hdr.mi_cols {
+            return;
+        }
+        let split = if tx == TX_4X4 || depth == MAX_VARTX_DEPTH {
+            false
+        } else {
+            let ctx = self.txfm_split_ctx(row, col, tx);
+            self.sd.read_symbol(&mut self.cdf.txfm_split[ctx]) == 1
+        };
+        let (w4, h4) = (tx_width(tx) >> 2, tx_height(tx) >> 2);
+        if split {
+            let sub = SPLIT_TX_SIZE[tx];
+            let (step_w, step_h) = (tx_width(sub) >> 2, tx_height(sub) >> 2);
+            for i in (0..h4).step_by(step_h) {
+                for j in (0..w4).step_by(step_w) {
+                    self.read_var_tx_size(row + i, col + j, sub, depth + 1);
+                }
+            }
+        } else {
+            for i in row..(row + h4).min(hdr.mi_rows) {
+                for j in col..(col + w4).min(hdr.mi_cols) {
+                    self.out.mi.get_mut(i, j).inter_tx_size = tx as u8;
+                }
+            }
+            self.b.tx_size = tx;
+        }
+    }
+
+    fn txfm_split_ctx(&self, row: usize, col: usize, tx: usize) -> usize {
+        let b = &self.b;
+        let above = self.above_tx_width(row, col) < tx_width(tx);
+        let left = self.left_tx_height(row, col) < tx_height(tx);
+        let size = block_width(b.mi_size).max(block_height(b.mi_size)).min(64);
+        let max_tx = tx_size(size.ilog2() as u8, size.ilog2() as u8);
+        let sqr_up = TX_SIZE_SQR_UP[tx];
+        (sqr_up != max_tx) as usize * 3 + (TX_64X64 - max_tx) * 6 + above as usize + left as usize
+    }
+
+    fn above_tx_width(&self, row: usize, col: usize) -> usize {
+        let b = &self.b;
+        if row == b.mi_row {
+            if !b.avail_u {
+                return 64;
+            }
+            let mi = self.mi(row - 1, col);
+            if mi.skip && mi.is_inter {
+                return block_width(mi.mi_size as usize);
+            }
+        }
+        tx_width(self.mi(row - 1, col).inter_tx_size as usize)
+    }
+
+    fn left_tx_height(&self, row: usize, col: usize) -> usize {
+        let b = &self.b;
+        if col == b.mi_col {
+            if !b.avail_l {
+                return 64;
+            }
+            let mi = self.mi(row, col - 1);
+            if mi.skip && mi.is_inter {
+                return block_height(mi.mi_size as usize);
+            }
+        }
+        tx_height(self.mi(row, col - 1).inter_tx_size as usize)
+    }
+
+    fn read_tx_size(&mut self, allow_select: bool) {
+        let mi_size = self.b.mi_size;
+        if self.b.lossless {
+            self.b.tx_size = TX_4X4;
+            return;
+        }
+        let max_rect = MAX_TX_SIZE_RECT[mi_size];
+        let max_depth = MAX_TX_DEPTH[mi_size] as usize;
+        self.b.tx_size = max_rect;
+        if mi_size > BLOCK_4X4 && allow_select && self.f.hdr.tx_mode == TX_MODE_SELECT {
+            let ctx = self.tx_depth_ctx(max_rect);
+            let depth = if max_depth == 1 {
+                self.sd.read_symbol(&mut self.cdf.tx_8x8[ctx])
+            } else {
+                self.sd.read_symbol(&mut self.cdf.tx_size[max_depth - 2][ctx])
+            };
+            for _ in 0..depth {
+                self.b.tx_size = SPLIT_TX_SIZE[self.b.tx_size];
+            }
+        }
+    }
+
+    fn tx_depth_ctx(&self, max_rect: usize) -> usize {
+        let b = &self.b;
+        let above_w = if b.avail_u {
+            let mi = self.mi(b.mi_row - 1, b.mi_col);
+            if mi.is_inter { block_width(mi.mi_size as usize) } else { self.above_tx_width(b.mi_row, b.mi_col) }
+        } else {
+            0
+        };
+        let left_h = if b.avail_l {
+            let mi = self.mi(b.mi_row, b.mi_col - 1);
+            if mi.is_inter { block_height(mi.mi_size as usize) } else { self.left_tx_height(b.mi_row, b.mi_col) }
+        } else {
+            0
+        };
+        (above_w >= tx_width(max_rect)) as usize + (left_h >= tx_height(max_rect)) as usize
+    }
+
+    /// Whether a reference has a different size than the current frame
+    pub fn is_scaled(&self, ref_frame: i8) -> bool {
+        let Some(r) = &self.f.refs[ref_frame as usize] else { return false };
+        let hdr = self.f.hdr;
+        r.header.upscaled_width != hdr.frame_width || r.header.frame_height != hdr.frame_height
+    }
+}
+
+pub(super) fn is_directional_mode(mode: u8) -> bool {
+    (V_PRED..=D67_PRED).contains(&mode)
+}
+
+pub(super) fn ceil_log2(x: u32) -> u32 {
+    if x < 2 { 0 } else { (x - 1).ilog2() + 1 }
+}
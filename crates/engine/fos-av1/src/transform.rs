@@ -0,0 +1,339 @@
+//! AV1 Inverse Transforms
+//!
+//! Butterfly forms of the inverse DCT (4 to 64 points), ADST (4 to 16
+//! points), identity and Walsh-Hadamard transforms, and the 2D transform
+//! adding a residual block to its prediction.
+
+use super::state::PlaneBuf;
+use super::tables::*;
+
+/// 4096 * cos(i * pi / 128)
+const COS128: [i32; 65] = [
+    4096, 4095, 4091, 4085, 4076, 4065, 4052, 4036, 4017, 3996, 3973, 3948, 3920, 3889, 3857, 3822, 3784, 3745,
+    3703, 3659, 3612, 3564, 3513, 3461, 3406, 3349, 3290, 3229, 3166, 3102, 3035, 2967, 2896, 2824, 2751, 2675,
+    2598, 2520, 2440, 2359, 2276, 2191, 2106, 2019, 1931, 1842, 1751, 1660, 1567, 1474, 1380, 1285, 1189, 1092,
+    995, 897, 799, 700, 601, 501, 401, 301, 201, 101, 0,
+];
+
+/// 4096 * 2 * sqrt(2) / 3 * sin(i * pi / 9)
+const SIN_PI_9: [i64; 5] = [0, 1321, 2482, 3344, 3803];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Dct,
+    Adst,
+    FlipAdst,
+    Identity,
+}
+
+/// Vertical and horizontal 1D transforms of a transform type
+fn kinds(tx_type: u8) -> (Kind, Kind) {
+    use Kind::*;
+    match tx_type {
+        DCT_DCT => (Dct, Dct),
+        ADST_DCT => (Adst, Dct),
+        DCT_ADST => (Dct, Adst),
+        ADST_ADST => (Adst, Adst),
+        FLIPADST_DCT => (FlipAdst, Dct),
+        DCT_FLIPADST => (Dct, FlipAdst),
+        FLIPADST_FLIPADST => (FlipAdst, FlipAdst),
+        ADST_FLIPADST => (Adst, FlipAdst),
+        FLIPADST_ADST => (FlipAdst, Adst),
+        V_DCT => (Dct, Identity),
+        H_DCT => (Identity, Dct),
+        V_ADST => (Adst, Identity),
+        H_ADST => (Identity, Adst),
+        V_FLIPADST => (FlipAdst, Identity),
+        H_FLIPADST => (Identity, FlipAdst),
+        _ => (Identity, Identity),
+    }
+}
+
+fn round2(x: i64, n: u32) -> i64 {
+    if n == 0 { x } else { (x + (1 << (n - 1))) >> n }
+}
+
+fn clamp_bits(x: i32, bits: u32) -> i32 {
+    x.clamp(-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+}
+
+/// Rounded weighted sum of two inputs with 12-bit weights
+fn btf(w0: i32, x: i32, w1: i32, y: i32) -> i32 {
+    round2(w0 as i64 * x as i64 + w1 as i64 * y as i64, 12) as i32
+}
+
+fn brev(bits: u32, x: usize) -> usize {
+    if bits == 0 { 0 } else { x.reverse_bits() >> (usize::BITS - bits) }
+}
+
+/// Inverse DCT: the even inputs form a DCT of half the size, the odd
+/// inputs go through rotations and butterflies of growing span
+fn idct(t: &mut [i32], range: u32) {
+    let n = t.len();
+    let half = n / 2;
+    let mut even = [0i32; 32];
+    let mut odd = [0i32; 32];
+    let lg = half.ilog2();
+    for i in 0..half {
+        even[i] = t[2 * i];
+        odd[i] = t[2 * brev(lg, i) + 1];
+    }
+    let even = &mut even[..half];
+    let odd = &mut odd[..half];
+    if half == 2 {
+        let (a, b) = (even[0], even[1]);
+        even[0] = btf(COS128[32], a, COS128[32], b);
+        even[1] = btf(COS128[32], a, -COS128[32], b);
+    } else {
+        idct(even, range);
+    }
+    for i in 0..half / 2 {
+        let s = (64 / n) * (1 + 4 * brev(lg - 1, i));
+        let (x, y) = (odd[i], odd[half - 1 - i]);
+        odd[i] = btf(COS128[64 - s], x, -COS128[s], y);
+        odd[half - 1 - i] = btf(COS128[s], x, COS128[64 - s], y);
+    }
+    let mut g = 2;
+    while g < half {
+        for blk in 0..half / g {
+            for j in 0..g / 2 {
+                let (a, c) = (blk * g + j, blk * g + g - 1 - j);
+                let (x, y) = (odd[a], odd[c]);
+                if blk % 2 == 0 {
+                    odd[a] = clamp_bits(x + y, range);
+                    odd[c] = clamp_bits(x - y, range);
+                } else {
+                    odd[a] = clamp_bits(y - x, range);
+                    odd[c] = clamp_bits(x + y, range);
+                }
+            }
+        }
+        let blocks = half / (4 * g);
+        for p in 0..half / 2 {
+            let q = p % (2 * g);
+            if q < g / 2 || q >= g + g / 2 {
+                continue;
+            }
+            let a = 16usize.checked_div(blocks).map_or(32, |d| d * (1 + 4 * brev(blocks.ilog2(), p / (2 * g))));
+            let r = half - 1 - p;
+            let (x, y) = (odd[p], odd[r]);
+            if q < g {
+                odd[p] = btf(-COS128[a], x, COS128[64 - a], y);
+                odd[r] = btf(COS128[64 - a], x, COS128[a], y);
+            } else {
+                odd[p] = btf(-COS128[64 - a], x, -COS128[a], y);
+                odd[r] = btf(-COS128[a], x, COS128[64 - a], y);
+            }
+        }
+        g *= 2;
+    }
+    for i in 0..half {
+        t[i] = clamp_bits(even[i] + odd[half - 1 - i], range);
+        t[n - 1 - i] = clamp_bits(even[i] - odd[half - 1 - i], range);
+    }
+}
+
+fn iadst4(t: &mut [i32]) {
+    let x: [i64; 4] = [t[0] as i64, t[1] as i64, t[2] as i64, t[3] as i64];
+    let s0 = SIN_PI_9[1] * x[0] + SIN_PI_9[4] * x[2] + SIN_PI_9[2] * x[3];
+    let s1 = SIN_PI_9[2] * x[0] - SIN_PI_9[1] * x[2] - SIN_PI_9[4] * x[3];
+    let s2 = SIN_PI_9[3] * (x[0] - x[2] + x[3]);
+    let s3 = SIN_PI_9[3] * x[1];
+    t[0] = round2(s0 + s3, 12) as i32;
+    t[1] = round2(s1 + s3, 12) as i32;
+    t[2] = round2(s2, 12) as i32;
+    t[3] = round2(s0 + s1 - s3, 12) as i32;
+}
+
+/// Rotation of an ADST butterfly stage; `flip` selects the mirrored form
+/// used in the second half of a block
+fn adst_rotate(x: &mut [i32], i: usize, a: usize, flip: bool) {
+    let (p, q) = (x[i], x[i + 1]);
+    if flip {
+        x[i] = btf(-COS128[64 - a], p, COS128[a], q);
+        x[i + 1] = btf(COS128[a], p, COS128[64 - a], q);
+    } else {
+        x[i] = btf(COS128[a], p, COS128[64 - a], q);
+        x[i + 1] = btf(COS128[64 - a], p, -COS128[a], q);
+    }
+}
+
+/// Inverse ADST of 8 or 16 points
+fn iadst(t: &mut [i32], range: u32) {
+    const OUT8: [usize; 8] = [0, 4, 6, 2, 3, 7, 5, 1];
+    const OUT16: [usize; 16] = [0, 8, 12, 4, 6, 14, 10, 2, 3, 11, 15, 7, 5, 13, 9, 1];
+    let n = t.len();
+    let mut x = [0i32; 16];
+    for i in 0..n {
+        x[i] = if i % 2 == 0 { t[n - 1 - i] } else { t[i - 1] };
+    }
+    let x = &mut x[..n];
+    for k in 0..n / 2 {
+        adst_rotate(x, 2 * k, (32 / n) * (1 + 4 * k), false);
+    }
+    let mut size = n;
+    while size >= 4 {
+        for s in (0..n).step_by(size) {
+            for i in s..s + size / 2 {
+                let (p, q) = (x[i], x[i + size / 2]);
+                x[i] = clamp_bits(p + q, range);
+                x[i + size / 2] = clamp_bits(p - q, range);
+            }
+            let pairs = size / 4;
+            for j in 0..pairs {
+                let i = s + size / 2 + 2 * j;
+                if size == 4 {
+                    adst_rotate(x, i, 32, false);
+                } else {
+                    let (a, flip) = if j < pairs / 2 { (j, false) } else { (j - pairs / 2, true) };
+                    adst_rotate(x, i, (128 / size) * (1 + 4 * a), flip);
+                }
+            }
+        }
+        size /= 2;
+    }
+    let order: &[usize] = if n == 8 { &OUT8 } else { &OUT16 };
+    for (i, &o) in order.iter().enumerate() {
+        t[i] = if i % 2 == 0 { x[o] } else { -x[o] };
+    }
+}
+
+fn identity(t: &mut [i32]) {
+    let n = t.len();
+    for v in t.iter_mut() {
+        let x = *v as i64;
+        *v = match n {
+            4 => round2(x * 5793, 12),
+            8 => x * 2,
+            16 => round2(x * 11586, 12),
+            _ => x * 4,
+        } as i32;
+    }
+}
+
+fn iwht4(t: &mut [i32]) {
+    let mut a = t[0];
+    let mut c = t[1];
+    let mut d = t[2];
+    let mut b = t[3];
+    a += c;
+    d -= b;
+    let e = (a - d) >> 1;
+    b = e - b;
+    c = e - c;
+    a -= b;
+    d += c;
+    t.copy_from_slice(&[a, b, c, d]);
+}
+
+fn transform_1d(t: &mut [i32], kind: Kind, range: u32) {
+    match kind {
+        Kind::Dct => idct(t, range),
+        Kind::Adst | Kind::FlipAdst if t.len() == 4 => iadst4(t),
+        Kind::Adst | Kind::FlipAdst => iadst(t, range),
+        Kind::Identity => identity(t),
+    }
+    if kind == Kind::FlipAdst {
+        t.reverse();
+    }
+}
+
+/// Inverse transform `coeffs` (row-major, at most 32x32 coded) and add the
+/// residual to the `w`x`h` block of `dst` at sample (`x`, `y`)
+#[allow(clippy::too_many_arguments)]
+pub(super) fn inverse_transform_add(
+    coeffs: &[i32],
+    tx: usize,
+    tx_type: u8,
+    lossless: bool,
+    bit_depth: u8,
+    dst: &mut PlaneBuf,
+    x: usize,
+    y: usize,
+) {
+    let (w, h) = (tx_width(tx), tx_height(tx));
+    let (cw, ch) = (w.min(32), h.min(32));
+    let (col_kind, row_kind) = kinds(tx_type);
+    let bd = bit_depth as u32;
+    let rect = (TX_WIDTH_LOG2[tx] as i32 - TX_HEIGHT_LOG2[tx] as i32).abs() == 1;
+    let row_shift = TRANSFORM_ROW_SHIFT[tx] as u32;
+    let col_range = (bd + 6).max(16);
+    let mut residual = vec![0i32; w * h];
+    let mut row = [0i32; 64];
+    for i in 0..ch {
+        let src = &coeffs[i * cw..(i + 1) * cw];
+        // Rows without coefficients transform to zero
+        if src.iter().all(|&c| c == 0) {
+            continue;
+        }
+        let row = &mut row[..w];
+        row.fill(0);
+        for (d, &c) in row.iter_mut().zip(src) {
+            let v = if rect {
+                round2(c as i64 * 2896, 12) as i32
+            } else if lossless {
+                c >> 2
+            } else {
+                c
+            };
+            *d = clamp_bits(v, bd + 8);
+        }
+        if lossless {
+            iwht4(row);
+        } else {
+            transform_1d(row, row_kind, bd + 8);
+        }
+        for (d, &v) in residual[i * w..(i + 1) * w].iter_mut().zip(row.iter()) {
+            *d = clamp_bits(round2(v as i64, row_shift) as i32, col_range);
+        }
+    }
+    let max = (1i32 << bd) - 1;
+    let stride = dst.cols;
+    let base = (y - dst.row0) * stride + x - dst.col0;
+    let mut col = [0i32; 64];
+    for j in 0..w {
+        let col = &mut col[..h];
+        for (i, v) in col.iter_mut().enumerate() {
+            *v = residual[i * w + j];
+        }
+        if lossless {
+            iwht4(col);
+        } else {
+            transform_1d(col, col_kind, col_range);
+        }
+        for (i, &v) in col.iter().enumerate() {
+            let r = if lossless { v } else { round2(v as i64, 4) as i32 };
+            let d = &mut dst.data[base + i * stride + j];
+            *d = (*d as i32 + r).clamp(0, max) as u16;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Grid;
+
+    #[test]
+    fn test_dc_only_is_flat() {
+        for tx in [TX_4X4, TX_8X8, TX_16X32, TX_64X64, TX_4X16] {
+            let (w, h) = (tx_width(tx), tx_height(tx));
+            let mut coeffs = vec![0; w.min(32) * h.min(32)];
+            coeffs[0] = 512;
+            let mut dst = Grid::new(0, 0, h, w, 128u16);
+            inverse_transform_add(&coeffs, tx, DCT_DCT, false, 8, &mut dst, 0, 0);
+            assert!(dst.data.iter().all(|&v| v == dst.data[0]), "tx {tx}");
+            assert!(dst.data[0] > 128);
+        }
+    }
+
+    #[test]
+    fn test_lossless_walsh_hadamard() {
+        // A single DC coefficient of 4 spreads evenly over the block
+        let mut coeffs = [0; 16];
+        coeffs[0] = 64;
+        let mut dst = Grid::new(0, 0, 4, 4, 100u16);
+        inverse_transform_add(&coeffs, TX_4X4, DCT_DCT, true, 8, &mut dst, 0, 0);
+        assert!(dst.data.iter().all(|&v| v == 104));
+    }
+}
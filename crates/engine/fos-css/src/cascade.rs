@@ -8,7 +8,11 @@
 use crate::{Stylesheet, Rule, Selector, SelectorPart, Combinator, Declaration, Specificity};
 use crate::properties::{PropertyId, PropertyValue};
 use crate::computed::ComputedStyle;
+use crate::viewport::Viewport;
 use fos_dom::{Document, NodeId, DomTree};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Mutex;
 
 /// Style resolver - computes styles for DOM elements
 pub struct StyleResolver {
@@ -16,6 +20,69 @@ pub struct StyleResolver {
     ua_styles: Stylesheet,
     /// Author stylesheets (page CSS)
     author_styles: Vec<Stylesheet>,
+    /// Viewport for resolving viewport-relative units
+    viewport: Viewport,
+    /// Interactive pseudo-classes forced on per element (DevTools)
+    forced_states: HashMap<NodeId, Vec<String>>,
+    /// Links to visited URLs, matching `:visited`
+    visited_links: HashSet<NodeId>,
+    /// Author rules that matched while usage is tracked, by stylesheet and
+    /// rule index (DevTools coverage)
+    rule_usage: Mutex<Option<HashSet<(usize, usize)>>>,
+}
+
+/// Whether an author rule matched any element while usage was tracked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleUsage {
+    /// Author stylesheet, by index in insertion order
+    pub stylesheet: usize,
+    /// Byte range of the rule in the stylesheet text
+    pub range: Range<usize>,
+    pub used: bool,
+}
+
+/// Stylesheet a matched rule came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleOrigin {
+    /// Browser default styles
+    UserAgent,
+    /// Author stylesheet, by index in insertion order
+    Author(usize),
+}
+
+/// A declaration of a matched rule and whether the cascade kept it
+#[derive(Debug, Clone)]
+pub struct TracedDeclaration {
+    pub declaration: Declaration,
+    /// A later or more specific declaration of the same property won
+    pub overridden: bool,
+}
+
+/// A selector that matched the traced element
+#[derive(Debug, Clone)]
+pub struct MatchedRuleTrace {
+    pub selector: String,
+    pub specificity: Specificity,
+    pub origin: RuleOrigin,
+    pub declarations: Vec<TracedDeclaration>,
+}
+
+/// Result of [`StyleResolver::trace_style`]
+#[derive(Debug, Clone)]
+pub struct StyleTrace {
+    /// Final computed style
+    pub style: ComputedStyle,
+    /// Matched rules, highest precedence first
+    pub rules: Vec<MatchedRuleTrace>,
+}
+
+/// One matched declaration during the cascade
+struct CascadeEntry<'a> {
+    decl: &'a Declaration,
+    specificity: Specificity,
+    source_order: usize,
+    /// Index into the matched selector list
+    rule: usize,
 }
 
 impl StyleResolver {
@@ -23,51 +90,162 @@ impl StyleResolver {
         Self {
             ua_styles: Self::default_ua_styles(),
             author_styles: Vec::new(),
+            viewport: Viewport::default(),
+            forced_states: HashMap::new(),
+            visited_links: HashSet::new(),
+            rule_usage: Mutex::new(None),
         }
     }
     
+    /// Set the viewport; styles must be recomputed if the change needs relayout
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+    
+    /// Current viewport
+    pub fn viewport(&self) -> &Viewport {
+        &self.viewport
+    }
+    
     /// Add an author stylesheet
     pub fn add_stylesheet(&mut self, stylesheet: Stylesheet) {
         self.author_styles.push(stylesheet);
     }
     
+    /// Force interactive pseudo-classes (`hover`, `focus`, ...) on an element
+    ///
+    /// An empty list clears the forced state of the element.
+    pub fn force_pseudo_state(&mut self, node_id: NodeId, states: &[&str]) {
+        if states.is_empty() {
+            self.forced_states.remove(&node_id);
+        } else {
+            let states = states.iter().map(|s| s.trim_start_matches(':').to_string()).collect();
+            self.forced_states.insert(node_id, states);
+        }
+    }
+    
+    /// Pseudo-classes currently forced on an element
+    pub fn forced_pseudo_state(&self, node_id: NodeId) -> &[String] {
+        self.forced_states.get(&node_id).map(|s| s.as_slice()).unwrap_or(&[])
+    }
+    
+    /// Set the links whose URLs are in the browsing history
+    ///
+    /// `:visited` rules only set colors on them; see
+    /// [`PropertyId::applies_to_visited`].
+    pub fn set_visited_links(&mut self, links: impl IntoIterator<Item = NodeId>) {
+        self.visited_links = links.into_iter().collect();
+    }
+    
+    /// Start recording which author rules match, for coverage reports
+    pub fn start_rule_usage_tracking(&mut self) {
+        *self.rule_usage.get_mut().unwrap() = Some(HashSet::new());
+    }
+    
+    /// Usage of every author rule with a known source range since tracking
+    /// started; empty when not tracking
+    pub fn rule_usage(&self) -> Vec<RuleUsage> {
+        let usage = self.rule_usage.lock().unwrap();
+        let Some(used) = usage.as_ref() else {
+            return Vec::new();
+        };
+        self.author_styles.iter().enumerate()
+            .flat_map(|(stylesheet, sheet)| sheet.rules.iter().enumerate().filter_map(move |(rule, r)| {
+                Some(RuleUsage { stylesheet, range: r.source_range.clone()?, used: used.contains(&(stylesheet, rule)) })
+            }))
+            .collect()
+    }
+    
+    /// Stop recording and return the final usage
+    pub fn stop_rule_usage_tracking(&mut self) -> Vec<RuleUsage> {
+        let usage = self.rule_usage();
+        *self.rule_usage.get_mut().unwrap() = None;
+        usage
+    }
+    
     /// Compute styles for an element
     pub fn compute_style(&self, tree: &DomTree, node_id: NodeId) -> ComputedStyle {
         let mut style = ComputedStyle::default();
         
+        // Apply declarations in order
+        for entry in self.cascade(tree, node_id, &mut Vec::new()) {
+            style.apply_declaration_in(entry.decl, &self.viewport);
+        }
+        
+        style
+    }
+    
+    /// Compute styles for an element and report which rules produced them
+    pub fn trace_style(&self, tree: &DomTree, node_id: NodeId) -> StyleTrace {
+        let mut style = ComputedStyle::default();
+        let mut selectors = Vec::new();
+        let entries = self.cascade(tree, node_id, &mut selectors);
+        
+        let mut rules: Vec<MatchedRuleTrace> = selectors.iter()
+            .map(|&(selector, origin)| MatchedRuleTrace {
+                selector: selector.text.clone(),
+                specificity: selector.specificity,
+                origin,
+                declarations: Vec::new(),
+            })
+            .collect();
+        
+        // Entries are in application order: the last one per property wins
+        let mut winner: HashMap<PropertyId, usize> = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            winner.insert(entry.decl.property, i);
+        }
+        
+        for (i, entry) in entries.iter().enumerate() {
+            style.apply_declaration_in(entry.decl, &self.viewport);
+            rules[entry.rule].declarations.push(TracedDeclaration {
+                declaration: entry.decl.clone(),
+                overridden: winner.get(&entry.decl.property) != Some(&i),
+            });
+        }
+        
+        // Most specific first, later sources before earlier ones
+        rules.reverse();
+        rules.sort_by_key(|r| std::cmp::Reverse(r.specificity));
+        
+        StyleTrace { style, rules }
+    }
+    
+    /// Matching declarations sorted into cascade order
+    fn cascade<'a>(
+        &'a self,
+        tree: &DomTree,
+        node_id: NodeId,
+        selectors: &mut Vec<(&'a Selector, RuleOrigin)>,
+    ) -> Vec<CascadeEntry<'a>> {
         // Collect all matching rules with specificity
-        let mut matches: Vec<(&Declaration, Specificity, usize)> = Vec::new();
+        let mut matches = Vec::new();
         
         // Match UA styles first
-        self.collect_matches(tree, node_id, &self.ua_styles, 0, &mut matches);
+        self.collect_matches(tree, node_id, &self.ua_styles, RuleOrigin::UserAgent, selectors, &mut matches);
         
         // Then author styles (higher precedence)
         for (i, stylesheet) in self.author_styles.iter().enumerate() {
-            self.collect_matches(tree, node_id, stylesheet, i + 1, &mut matches);
+            self.collect_matches(tree, node_id, stylesheet, RuleOrigin::Author(i), selectors, &mut matches);
         }
         
         // Sort by specificity and source order
         matches.sort_by(|a, b| {
             // First compare by !important
-            match (a.0.important, b.0.important) {
+            match (a.decl.important, b.decl.important) {
                 (true, false) => std::cmp::Ordering::Greater,
                 (false, true) => std::cmp::Ordering::Less,
                 _ => {
                     // Then by specificity
-                    match a.1.cmp(&b.1) {
-                        std::cmp::Ordering::Equal => a.2.cmp(&b.2), // Source order
+                    match a.specificity.cmp(&b.specificity) {
+                        std::cmp::Ordering::Equal => a.source_order.cmp(&b.source_order), // Source order
                         other => other,
                     }
                 }
             }
         });
         
-        // Apply declarations in order
-        for (decl, _, _) in matches {
-            style.apply_declaration(decl);
-        }
-        
-        style
+        matches
     }
     
     fn collect_matches<'a>(
@@ -75,14 +253,29 @@ impl StyleResolver {
         tree: &DomTree,
         node_id: NodeId,
         stylesheet: &'a Stylesheet,
-        source_order: usize,
-        matches: &mut Vec<(&'a Declaration, Specificity, usize)>,
+        origin: RuleOrigin,
+        selectors: &mut Vec<(&'a Selector, RuleOrigin)>,
+        matches: &mut Vec<CascadeEntry<'a>>,
     ) {
-        for rule in &stylesheet.rules {
+        let source_order = match origin {
+            RuleOrigin::UserAgent => 0,
+            RuleOrigin::Author(i) => i + 1,
+        };
+        let mut usage = self.rule_usage.lock().unwrap();
+        for (rule_index, rule) in stylesheet.rules.iter().enumerate() {
             for selector in &rule.selectors {
                 if self.matches_selector(tree, node_id, selector) {
+                    if let (Some(used), RuleOrigin::Author(sheet)) = (usage.as_mut(), origin) {
+                        used.insert((sheet, rule_index));
+                    }
+                    let index = selectors.len();
+                    selectors.push((selector, origin));
+                    let visited = selector.parts.iter().any(|p| matches!(p, SelectorPart::PseudoClass(c) if c == "visited"));
                     for decl in &rule.declarations {
-                        matches.push((decl, selector.specificity, source_order));
+                        if visited && !decl.property.applies_to_visited() {
+                            continue;
+                        }
+                        matches.push(CascadeEntry { decl, specificity: selector.specificity, source_order, rule: index });
                     }
                 }
             }
@@ -185,11 +378,29 @@ impl StyleResolver {
             }
             "empty" => node.first_child == NodeId::NONE,
             "root" => node.parent == NodeId::ROOT,
-            // Other pseudo-classes would need more context (hover, focus, etc.)
+            // Interactive states only match when forced from DevTools
+            "hover" | "focus" | "active" | "focus-visible" | "focus-within" => {
+                self.forced_pseudo_state(node_id).iter().any(|s| s == pseudo)
+            }
+            "visited" => self.is_visited(node_id),
+            "link" => self.is_link(tree, node_id) && !self.is_visited(node_id),
+            // Other pseudo-classes would need more context
             _ => false,
         }
     }
     
+    fn is_visited(&self, node_id: NodeId) -> bool {
+        self.visited_links.contains(&node_id)
+            || self.forced_pseudo_state(node_id).iter().any(|s| s == "visited")
+    }
+    
+    /// `<a>` or `<area>` with an `href`
+    fn is_link(&self, tree: &DomTree, node_id: NodeId) -> bool {
+        let Some(elem) = tree.get(node_id).and_then(|n| n.as_element()) else { return false };
+        let has_href = tree.interner().intern_lookup("href").is_some_and(|href| elem.get_attr(href).is_some());
+        matches!(tree.resolve(elem.name.local), "a" | "area") && has_href
+    }
+    
     /// Default user-agent styles
     fn default_ua_styles() -> Stylesheet {
         use crate::{Rule, Declaration};
@@ -213,6 +424,7 @@ impl StyleResolver {
                             important: false,
                         },
                     ],
+                    source_range: None,
                 },
                 // Inline elements
                 Rule {
@@ -230,6 +442,7 @@ impl StyleResolver {
                             important: false,
                         },
                     ],
+                    source_range: None,
                 },
                 // Hidden elements
                 Rule {
@@ -247,6 +460,7 @@ impl StyleResolver {
                             important: false,
                         },
                     ],
+                    source_range: None,
                 },
             ],
         }
@@ -258,3 +472,127 @@ impl Default for StyleResolver {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::properties::Keyword;
+    
+    fn rule(text: &str, specificity: Specificity, parts: Vec<SelectorPart>, display: Keyword) -> Rule {
+        Rule {
+            selectors: vec![Selector { text: text.into(), specificity, parts }],
+            declarations: vec![Declaration {
+                property: PropertyId::Display,
+                value: PropertyValue::Keyword(display),
+                important: false,
+            }],
+            source_range: None,
+        }
+    }
+    
+    #[test]
+    fn test_trace_marks_overridden_declarations() {
+        let mut tree = DomTree::new();
+        let div = tree.create_element("div");
+        tree.append_child(tree.root(), div);
+        
+        let mut resolver = StyleResolver::new();
+        resolver.add_stylesheet(Stylesheet {
+            rules: vec![rule("div", Specificity(0, 0, 1), vec![SelectorPart::Type("div".into())], Keyword::Flex)],
+        });
+        
+        let trace = resolver.trace_style(&tree, div);
+        let selectors: Vec<_> = trace.rules.iter().map(|r| (r.selector.as_str(), r.origin)).collect();
+        assert_eq!(selectors[0], ("div", RuleOrigin::Author(0)));
+        assert_eq!(selectors[1].1, RuleOrigin::UserAgent);
+        assert!(!trace.rules[0].declarations[0].overridden);
+        assert!(trace.rules[1].declarations[0].overridden);
+        assert_eq!(trace.style.display, crate::computed::Display::Flex);
+        assert_eq!(resolver.compute_style(&tree, div).display, crate::computed::Display::Flex);
+    }
+    
+    #[test]
+    fn test_forced_hover_state() {
+        let mut tree = DomTree::new();
+        let span = tree.create_element("span");
+        tree.append_child(tree.root(), span);
+        
+        let mut resolver = StyleResolver::new();
+        resolver.add_stylesheet(Stylesheet {
+            rules: vec![rule(
+                "span:hover",
+                Specificity(0, 1, 1),
+                vec![SelectorPart::Type("span".into()), SelectorPart::PseudoClass("hover".into())],
+                Keyword::Block,
+            )],
+        });
+        assert_eq!(resolver.trace_style(&tree, span).rules.len(), 1);
+        
+        resolver.force_pseudo_state(span, &[":hover"]);
+        assert_eq!(resolver.forced_pseudo_state(span), ["hover".to_string()]);
+        assert_eq!(resolver.trace_style(&tree, span).rules[0].selector, "span:hover");
+        
+        resolver.force_pseudo_state(span, &[]);
+        assert!(resolver.forced_pseudo_state(span).is_empty());
+    }
+    
+    #[test]
+    fn test_visited_links_only_set_colors() {
+        let mut tree = DomTree::new();
+        let link = tree.create_element("a");
+        tree.set_attribute(link, "href", "https://example.com/");
+        tree.append_child(tree.root(), link);
+        
+        let mut visited = rule(
+            "a:visited",
+            Specificity(0, 1, 1),
+            vec![SelectorPart::Type("a".into()), SelectorPart::PseudoClass("visited".into())],
+            Keyword::None,
+        );
+        visited.declarations.push(Declaration {
+            property: PropertyId::Color,
+            value: PropertyValue::Color(crate::properties::Color::rgb(85, 26, 139)),
+            important: false,
+        });
+        let unvisited = rule(
+            "a:link",
+            Specificity(0, 1, 1),
+            vec![SelectorPart::Type("a".into()), SelectorPart::PseudoClass("link".into())],
+            Keyword::Flex,
+        );
+        let mut resolver = StyleResolver::new();
+        resolver.add_stylesheet(Stylesheet { rules: vec![visited, unvisited] });
+        assert_eq!(resolver.trace_style(&tree, link).rules[0].selector, "a:link");
+        
+        resolver.set_visited_links([link]);
+        let trace = resolver.trace_style(&tree, link);
+        assert_eq!(trace.rules[0].selector, "a:visited");
+        assert_eq!(trace.rules[0].declarations.len(), 1);
+        assert_eq!((trace.style.color.r, trace.style.color.g, trace.style.color.b), (85, 26, 139));
+        assert_ne!(trace.style.display, crate::computed::Display::None);
+    }
+    
+    #[test]
+    fn test_rule_usage_tracking() {
+        let mut tree = DomTree::new();
+        let div = tree.create_element("div");
+        tree.append_child(tree.root(), div);
+        
+        let css = "div { display: flex } p { display: block }";
+        let mut div_rule = rule("div", Specificity(0, 0, 1), vec![SelectorPart::Type("div".into())], Keyword::Flex);
+        div_rule.source_range = Some(0..21);
+        let mut p_rule = rule("p", Specificity(0, 0, 1), vec![SelectorPart::Type("p".into())], Keyword::Block);
+        p_rule.source_range = Some(22..42);
+        
+        let mut resolver = StyleResolver::new();
+        resolver.add_stylesheet(Stylesheet { rules: vec![div_rule, p_rule] });
+        assert!(resolver.rule_usage().is_empty());
+        
+        resolver.start_rule_usage_tracking();
+        resolver.compute_style(&tree, div);
+        let usage = resolver.stop_rule_usage_tracking();
+        let used: Vec<(&str, bool)> = usage.iter().map(|u| (&css[u.range.clone()], u.used)).collect();
+        assert_eq!(used, vec![("div { display: flex }", true), ("p { display: block }", false)]);
+        assert!(resolver.rule_usage().is_empty());
+    }
+}
@@ -4,6 +4,8 @@
 //! Uses compact representation for memory efficiency.
 
 use crate::properties::{PropertyId, PropertyValue, Keyword, Length, LengthUnit, Color};
+use crate::cow_style::Hyphens;
+use crate::viewport::Viewport;
 use crate::Declaration;
 
 /// Computed style for an element
@@ -31,11 +33,17 @@ pub struct ComputedStyle {
     // Colors
     pub color: Color,
     pub background_color: Color,
+    pub accent_color: AccentColor,
+    pub color_scheme: ColorScheme,
+    
+    // Form controls
+    pub appearance: Appearance,
     
     // Text
     pub font_size: f32,        // in pixels
     pub font_weight: u16,      // 100-900
     pub line_height: f32,      // multiplier
+    pub hyphens: Hyphens,
     
     // Flex
     pub flex_direction: FlexDirection,
@@ -47,6 +55,7 @@ pub struct ComputedStyle {
     pub visibility: Visibility,
     pub opacity: f32,
     pub overflow: Overflow,
+    pub overflow_anchor: OverflowAnchor,
     pub z_index: Option<i32>,
     
     // Positioning
@@ -95,6 +104,11 @@ impl PropertyMask {
     pub const MAX_HEIGHT: u64 = 1 << 25;
     pub const BORDER_WIDTH: u64 = 1 << 26;
     pub const FLEX_WRAP: u64 = 1 << 27;
+    pub const OVERFLOW_ANCHOR: u64 = 1 << 28;
+    pub const ACCENT_COLOR: u64 = 1 << 29;
+    pub const COLOR_SCHEME: u64 = 1 << 30;
+    pub const APPEARANCE: u64 = 1 << 31;
+    pub const HYPHENS: u64 = 1 << 32;
     
     /// Create empty mask
     pub fn new() -> Self {
@@ -135,6 +149,15 @@ impl PropertyMask {
 impl ComputedStyle {
     /// Apply a declaration to this computed style
     pub fn apply_declaration(&mut self, decl: &Declaration) {
+        self.apply(decl, None);
+    }
+    
+    /// Apply a declaration, resolving viewport units against `viewport`
+    pub fn apply_declaration_in(&mut self, decl: &Declaration, viewport: &Viewport) {
+        self.apply(decl, Some(viewport));
+    }
+    
+    fn apply(&mut self, decl: &Declaration, viewport: Option<&Viewport>) {
         match decl.property {
             PropertyId::Display => {
                 if let PropertyValue::Keyword(kw) = &decl.value {
@@ -143,8 +166,11 @@ impl ComputedStyle {
                         Keyword::Block => Display::Block,
                         Keyword::Inline => Display::Inline,
                         Keyword::InlineBlock => Display::InlineBlock,
+                        Keyword::FlowRoot => Display::FlowRoot,
                         Keyword::Flex => Display::Flex,
+                        Keyword::InlineFlex => Display::InlineFlex,
                         Keyword::Grid => Display::Grid,
+                        Keyword::InlineGrid => Display::InlineGrid,
                         Keyword::Contents => Display::Contents,
                         _ => return,
                     };
@@ -180,9 +206,40 @@ impl ComputedStyle {
             }
             PropertyId::FontSize => {
                 if let PropertyValue::Length(len) = &decl.value {
-                    self.font_size = Self::length_to_px(len, self.font_size);
+                    self.font_size = Self::length_to_px(len, self.font_size, viewport);
                 }
             }
+            PropertyId::FontWeight => {
+                self.font_weight = match &decl.value {
+                    PropertyValue::Number(n) => n.clamp(1.0, 1000.0) as u16,
+                    PropertyValue::Keyword(Keyword::Normal) => 400,
+                    PropertyValue::Keyword(Keyword::Bold) => 700,
+                    // Relative weights step across the 350/550/750 thresholds
+                    PropertyValue::Keyword(Keyword::Bolder) => match self.font_weight {
+                        0..350 => 400,
+                        350..550 => 700,
+                        _ => 900,
+                    },
+                    PropertyValue::Keyword(Keyword::Lighter) => match self.font_weight {
+                        0..550 => 100,
+                        550..750 => 400,
+                        _ => 700,
+                    },
+                    _ => return,
+                };
+            }
+            PropertyId::LineHeight => {
+                self.line_height = match &decl.value {
+                    PropertyValue::Number(n) => *n,
+                    PropertyValue::Keyword(Keyword::Normal) => 1.2,
+                    PropertyValue::Length(len) if len.unit == LengthUnit::Percent => len.value / 100.0,
+                    PropertyValue::Length(len) if len.unit == LengthUnit::Em => len.value,
+                    PropertyValue::Length(len) if self.font_size > 0.0 => {
+                        Self::length_to_px(len, self.font_size, viewport) / self.font_size
+                    }
+                    _ => return,
+                };
+            }
             PropertyId::Opacity => {
                 if let PropertyValue::Number(n) = &decl.value {
                     self.opacity = n.clamp(0.0, 1.0);
@@ -224,6 +281,69 @@ impl ComputedStyle {
                     };
                 }
             }
+            PropertyId::OverflowAnchor => {
+                if let PropertyValue::Keyword(kw) = &decl.value {
+                    self.overflow_anchor = match kw {
+                        Keyword::Auto => OverflowAnchor::Auto,
+                        Keyword::None => OverflowAnchor::None,
+                        _ => return,
+                    };
+                }
+            }
+            PropertyId::AccentColor => {
+                self.accent_color = match &decl.value {
+                    PropertyValue::Keyword(Keyword::Auto) => AccentColor::Auto,
+                    PropertyValue::Color(c) => AccentColor::Color(*c),
+                    _ => return,
+                };
+                self.property_mask.set(PropertyMask::ACCENT_COLOR);
+            }
+            PropertyId::ColorScheme => {
+                self.color_scheme = match &decl.value {
+                    PropertyValue::Keyword(Keyword::Normal) => ColorScheme::NORMAL,
+                    PropertyValue::Keyword(kw) => match ColorScheme::from_keywords(&[*kw]) {
+                        Some(scheme) => scheme,
+                        None => return,
+                    },
+                    PropertyValue::List(values) => {
+                        let keywords: Vec<Keyword> = values.iter()
+                            .filter_map(|v| match v {
+                                PropertyValue::Keyword(kw) => Some(*kw),
+                                _ => None,
+                            })
+                            .collect();
+                        match ColorScheme::from_keywords(&keywords) {
+                            Some(scheme) => scheme,
+                            None => return,
+                        }
+                    }
+                    _ => return,
+                };
+                self.property_mask.set(PropertyMask::COLOR_SCHEME);
+            }
+            PropertyId::Appearance => {
+                if let PropertyValue::Keyword(kw) = &decl.value {
+                    self.appearance = match kw {
+                        Keyword::Auto => Appearance::Auto,
+                        Keyword::None => Appearance::None,
+                        Keyword::Textfield => Appearance::Textfield,
+                        Keyword::MenulistButton => Appearance::MenulistButton,
+                        _ => return,
+                    };
+                    self.property_mask.set(PropertyMask::APPEARANCE);
+                }
+            }
+            PropertyId::Hyphens => {
+                if let PropertyValue::Keyword(kw) = &decl.value {
+                    self.hyphens = match kw {
+                        Keyword::None => Hyphens::None,
+                        Keyword::Manual => Hyphens::Manual,
+                        Keyword::Auto => Hyphens::Auto,
+                        _ => return,
+                    };
+                    self.property_mask.set(PropertyMask::HYPHENS);
+                }
+            }
             // Handle shorthand properties
             PropertyId::Margin => {
                 self.margin = Self::value_to_edges(&decl.value);
@@ -316,7 +436,10 @@ impl ComputedStyle {
         }
     }
     
-    fn length_to_px(len: &Length, parent_font_size: f32) -> f32 {
+    fn length_to_px(len: &Length, parent_font_size: f32, viewport: Option<&Viewport>) -> f32 {
+        if let Some(px) = viewport.and_then(|v| v.resolve(len.value, len.unit)) {
+            return px;
+        }
         match len.unit {
             LengthUnit::Px => len.value,
             LengthUnit::Em => len.value * parent_font_size,
@@ -334,12 +457,39 @@ pub enum Display {
     Block,
     Inline,
     InlineBlock,
+    /// Block box establishing a new block formatting context
+    FlowRoot,
     Flex,
+    InlineFlex,
     Grid,
+    InlineGrid,
     None,
+    /// No box of its own; children are laid out as if in the parent
     Contents,
 }
 
+impl Display {
+    /// Whether the box participates in an inline formatting context
+    pub fn is_inline_level(&self) -> bool {
+        matches!(self, Display::Inline | Display::InlineBlock | Display::InlineFlex | Display::InlineGrid)
+    }
+
+    /// Whether the box establishes a new block formatting context for its
+    /// contents (so child margins don't collapse through it)
+    pub fn establishes_bfc(&self) -> bool {
+        matches!(
+            self,
+            Display::InlineBlock | Display::FlowRoot | Display::Flex | Display::InlineFlex
+                | Display::Grid | Display::InlineGrid
+        )
+    }
+
+    /// Whether the element generates a box at all
+    pub fn generates_box(&self) -> bool {
+        !matches!(self, Display::None | Display::Contents)
+    }
+}
+
 /// Position property values
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum Position {
@@ -434,3 +584,78 @@ pub enum Overflow {
     Auto,
     Clip,
 }
+
+/// Whether a box may be chosen as a scroll anchor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowAnchor {
+    #[default]
+    Auto,
+    None,
+}
+
+/// `accent-color`: tint of checked and filled parts of form controls
+#[derive(Debug, Clone, Copy, Default)]
+pub enum AccentColor {
+    /// Platform accent
+    #[default]
+    Auto,
+    Color(Color),
+}
+
+/// `color-scheme`: color schemes an element can be rendered in
+///
+/// `normal` (neither scheme listed) renders with the light defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorScheme {
+    pub light: bool,
+    pub dark: bool,
+    /// Forbids the user agent from forcing another scheme on the element
+    pub only: bool,
+}
+
+impl ColorScheme {
+    pub const NORMAL: ColorScheme = ColorScheme { light: false, dark: false, only: false };
+    
+    /// Build from the keywords of a declaration (`light dark`, `only dark`, ...)
+    pub fn from_keywords(keywords: &[Keyword]) -> Option<Self> {
+        let mut scheme = Self::NORMAL;
+        for kw in keywords {
+            match kw {
+                Keyword::Light => scheme.light = true,
+                Keyword::Dark => scheme.dark = true,
+                Keyword::Only => scheme.only = true,
+                _ => return None,
+            }
+        }
+        // `only` must accompany a scheme
+        (scheme.light || scheme.dark).then_some(scheme)
+    }
+    
+    /// Whether the element uses dark colors, given the user's preference
+    pub fn uses_dark(&self, prefers_dark: bool) -> bool {
+        match (self.light, self.dark) {
+            (true, true) => prefers_dark,
+            (false, true) => true,
+            _ => false,
+        }
+    }
+}
+
+/// `appearance`: whether form controls get native painting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Appearance {
+    /// Native look; the compat values (`button`, `checkbox`, ...) behave like this
+    #[default]
+    Auto,
+    /// No native painting: the control is styled like any other element
+    None,
+    Textfield,
+    MenulistButton,
+}
+
+impl Appearance {
+    /// Whether the control is painted natively
+    pub fn is_native(&self) -> bool {
+        *self != Appearance::None
+    }
+}
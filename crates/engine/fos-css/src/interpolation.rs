@@ -0,0 +1,994 @@
+//! Value Interpolation
+//!
+//! Animatable CSS values shared by transitions and Web Animations. Values are
+//! parsed from CSS text, interpolated by type and serialized back:
+//! - lengths and numbers per unit
+//! - colors in premultiplied sRGB when both use legacy syntax, else in Oklab
+//! - transform lists function by function, or through 2D matrix decomposition
+//!   when the lists do not line up
+//! - shadow lists, padded with transparent shadows
+//! - gradients of the same type and stop count, stop by stop
+//!
+//! Everything else is discrete and flips at 50%.
+//! https://www.w3.org/TR/css-values-4/#interpolation
+
+use std::fmt;
+use crate::properties::Color;
+use crate::web_animations::Unit;
+
+/// How a property animates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationType {
+    /// Cannot be animated or transitioned
+    NotAnimatable,
+    /// Interpolated by value type, discrete when the values do not combine
+    ByComputedValue,
+    /// Like `ByComputedValue`, rounded to an integer
+    Integer,
+    /// `visibility`: visible for the whole interval when either end is
+    Visibility,
+    /// `display`: the value that is not `none` for the whole interval
+    Display,
+}
+
+impl AnimationType {
+    pub fn of(property: &str) -> Self {
+        match property {
+            "visibility" => Self::Visibility,
+            "display" => Self::Display,
+            "z-index" | "order" | "orphans" | "widows" | "column-count" => Self::Integer,
+            "will-change" | "direction" | "unicode-bidi" | "contain" => Self::NotAnimatable,
+            _ if property.starts_with("transition") || property.starts_with("animation") => Self::NotAnimatable,
+            _ => Self::ByComputedValue,
+        }
+    }
+}
+
+/// Animatable value
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimatableValue {
+    /// Length, angle, time, percentage or plain number
+    Length(f64, Unit),
+    Color(AnimatedColor),
+    /// Transform list; empty for `none`
+    Transform(Vec<TransformFunction>),
+    /// Shadow list; empty for `none`
+    Shadows(Vec<Shadow>),
+    Gradient(Gradient),
+    /// Space-separated lengths and colors, e.g. `10px 20px`
+    List(Vec<AnimatableValue>),
+    /// Anything else, animated discretely
+    Keyword(String),
+}
+
+impl AnimatableValue {
+    /// Parse the CSS text of a property value
+    pub fn parse(property: &str, css: &str) -> Self {
+        let css = css.trim();
+        let typed = match property {
+            "transform" => parse_transform_list(css).map(Self::Transform),
+            "box-shadow" | "text-shadow" => parse_shadows(css).map(Self::Shadows),
+            _ => None,
+        };
+        typed
+            .or_else(|| Gradient::parse(css).map(Self::Gradient))
+            .or_else(|| Self::parse_component(css))
+            .or_else(|| {
+                let parts = tokens(css);
+                if parts.len() < 2 { return None; }
+                parts.iter().map(|part| Self::parse_component(part)).collect::<Option<Vec<_>>>().map(Self::List)
+            })
+            .unwrap_or_else(|| Self::Keyword(css.to_string()))
+    }
+    
+    fn parse_component(css: &str) -> Option<Self> {
+        parse_length(css).map(|(v, u)| Self::Length(v, u))
+            .or_else(|| AnimatedColor::parse(css).map(Self::Color))
+    }
+    
+    /// Value at `t` from `self` to `to`, or `None` when the two cannot be
+    /// interpolated and the property animates discretely
+    pub fn interpolate(&self, to: &Self, t: f64) -> Option<Self> {
+        if self == to {
+            return Some(self.clone());
+        }
+        Some(match (self, to) {
+            (Self::Length(a, ua), Self::Length(b, ub)) => {
+                let (a, b, unit) = (a, b, common_unit(*a, *ua, *b, *ub)?);
+                Self::Length(lerp(*a, *b, t), unit)
+            }
+            (Self::Color(a), Self::Color(b)) => Self::Color(a.interpolate(b, t)),
+            (Self::Transform(a), Self::Transform(b)) => Self::Transform(interpolate_transforms(a, b, t)?),
+            (Self::Shadows(a), Self::Shadows(b)) => Self::Shadows(interpolate_shadows(a, b, t)?),
+            (Self::Gradient(a), Self::Gradient(b)) => Self::Gradient(a.interpolate(b, t)?),
+            (Self::List(a), Self::List(b)) if a.len() == b.len() => {
+                Self::List(a.iter().zip(b).map(|(a, b)| a.interpolate(b, t)).collect::<Option<_>>()?)
+            }
+            _ => return None,
+        })
+    }
+    
+    /// Whether values between `self` and `to` exist, rather than a flip
+    pub fn is_interpolable_with(&self, to: &Self) -> bool {
+        self != to && self.interpolate(to, 0.5).is_some()
+    }
+}
+
+impl fmt::Display for AnimatableValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length(value, unit) => write!(f, "{}{}", number(*value), unit_suffix(*unit)),
+            Self::Color(color) => write!(f, "{}", color),
+            Self::Transform(functions) if functions.is_empty() => f.write_str("none"),
+            Self::Transform(functions) => write_joined(f, functions, " "),
+            Self::Shadows(shadows) if shadows.is_empty() => f.write_str("none"),
+            Self::Shadows(shadows) => write_joined(f, shadows, ", "),
+            Self::Gradient(gradient) => write!(f, "{}", gradient),
+            Self::List(values) => write_joined(f, values, " "),
+            Self::Keyword(keyword) => f.write_str(keyword),
+        }
+    }
+}
+
+/// Value of `property` at `t`, falling back to a flip at 50% when the
+/// values cannot be interpolated
+pub fn interpolate_property(property: &str, from: &AnimatableValue, to: &AnimatableValue, t: f64) -> AnimatableValue {
+    let discrete = || if t < 0.5 { from.clone() } else { to.clone() };
+    let inside = t > 0.0 && t < 1.0;
+    let is = |value: &AnimatableValue, keyword: &str| matches!(value, AnimatableValue::Keyword(k) if k == keyword);
+    
+    match AnimationType::of(property) {
+        AnimationType::Visibility if inside && (is(from, "visible") || is(to, "visible")) => {
+            AnimatableValue::Keyword("visible".to_string())
+        }
+        AnimationType::Display if inside && is(from, "none") != is(to, "none") => {
+            if is(from, "none") { to.clone() } else { from.clone() }
+        }
+        AnimationType::Integer => match from.interpolate(to, t) {
+            Some(AnimatableValue::Length(value, unit)) => AnimatableValue::Length(value.round(), unit),
+            _ => discrete(),
+        },
+        _ => from.interpolate(to, t).unwrap_or_else(discrete),
+    }
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Shared unit of two lengths; a zero adopts the other's unit
+fn common_unit(a: f64, ua: Unit, b: f64, ub: Unit) -> Option<Unit> {
+    if ua == ub { Some(ua) } else if a == 0.0 { Some(ub) } else if b == 0.0 { Some(ua) } else { None }
+}
+
+/// Shortest form of a number, at most four decimals
+fn number(value: f64) -> String {
+    let rounded = (value * 10000.0).round() / 10000.0;
+    if rounded == 0.0 { "0".to_string() } else { rounded.to_string() }
+}
+
+fn unit_suffix(unit: Unit) -> &'static str {
+    match unit {
+        Unit::Px => "px",
+        Unit::Em => "em",
+        Unit::Rem => "rem",
+        Unit::Percent => "%",
+        Unit::Deg => "deg",
+        Unit::Rad => "rad",
+        Unit::Ms => "ms",
+        Unit::S => "s",
+        Unit::None => "",
+    }
+}
+
+fn write_joined<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T], separator: &str) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 { f.write_str(separator)?; }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// Parse a number with an optional unit
+pub fn parse_length(s: &str) -> Option<(f64, Unit)> {
+    let s = s.trim();
+    const UNITS: [(&str, Unit); 8] = [
+        ("px", Unit::Px), ("rem", Unit::Rem), ("em", Unit::Em), ("%", Unit::Percent),
+        ("deg", Unit::Deg), ("rad", Unit::Rad), ("ms", Unit::Ms), ("s", Unit::S),
+    ];
+    for (suffix, unit) in UNITS {
+        if let Some(Ok(value)) = s.strip_suffix(suffix).map(str::parse::<f64>) {
+            return value.is_finite().then_some((value, unit));
+        }
+    }
+    s.parse().ok().filter(|n: &f64| n.is_finite()).map(|n| (n, Unit::None))
+}
+
+/// Parse an angle in degrees; unitless only for zero
+fn parse_angle(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let (value, factor) = if let Some(v) = s.strip_suffix("deg") {
+        (v, 1.0)
+    } else if let Some(v) = s.strip_suffix("grad") {
+        (v, 0.9)
+    } else if let Some(v) = s.strip_suffix("rad") {
+        (v, 180.0 / std::f64::consts::PI)
+    } else if let Some(v) = s.strip_suffix("turn") {
+        (v, 360.0)
+    } else {
+        return (s == "0").then_some(0.0);
+    };
+    value.parse::<f64>().ok().map(|v| v * factor)
+}
+
+/// Split at `separator` outside parentheses
+fn split_top_level(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if c == separator && depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts
+}
+
+/// Whitespace-separated tokens, keeping function arguments together
+fn tokens(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, None);
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if c.is_whitespace() && depth == 0 => {
+                if let Some(begin) = start.take() { parts.push(&s[begin..i]); }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(begin) = start { parts.push(&s[begin..]); }
+    parts
+}
+
+/// Arguments of `name(...)`
+fn function_args<'a>(s: &'a str, name: &str) -> Option<&'a str> {
+    s.strip_prefix(name)?.trim_start().strip_prefix('(')?.strip_suffix(')')
+}
+
+// ============================================================================
+// Colors
+// ============================================================================
+
+/// Space a color is interpolated in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterpolationSpace {
+    /// Legacy colors (hex, names, `rgb()`, `hsl()`)
+    Srgb,
+    Oklab,
+}
+
+/// Color with float components in its interpolation space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimatedColor {
+    pub space: InterpolationSpace,
+    /// r, g, b from 0 to 1, or L, a, b
+    pub components: [f64; 3],
+    pub alpha: f64,
+}
+
+impl From<Color> for AnimatedColor {
+    fn from(c: Color) -> Self {
+        Self {
+            space: InterpolationSpace::Srgb,
+            components: [c.r as f64 / 255.0, c.g as f64 / 255.0, c.b as f64 / 255.0],
+            alpha: c.a as f64 / 255.0,
+        }
+    }
+}
+
+impl AnimatedColor {
+    pub fn parse(css: &str) -> Option<Self> {
+        let css = css.trim().to_ascii_lowercase();
+        if css.starts_with('#') {
+            return Color::from_hex(&css).map(Self::from);
+        }
+        if let Some(args) = function_args(&css, "rgba").or_else(|| function_args(&css, "rgb")) {
+            let (channels, alpha) = color_args(args)?;
+            let channel = |s: &str| match s.strip_suffix('%') {
+                Some(p) => p.parse::<f64>().ok().map(|p| p / 100.0),
+                None => s.parse::<f64>().ok().map(|v| v / 255.0),
+            };
+            let rgb = [channel(channels[0])?, channel(channels[1])?, channel(channels[2])?];
+            return Some(Self::srgb(rgb, alpha));
+        }
+        if let Some(args) = function_args(&css, "hsla").or_else(|| function_args(&css, "hsl")) {
+            let (channels, alpha) = color_args(args)?;
+            let hue = parse_angle(channels[0]).or_else(|| channels[0].parse().ok())?;
+            let percent = |s: &str| s.trim_end_matches('%').parse::<f64>().ok().map(|p| p / 100.0);
+            return Some(Self::srgb(hsl_to_rgb(hue, percent(channels[1])?, percent(channels[2])?), alpha));
+        }
+        if let Some(args) = function_args(&css, "oklab") {
+            let (channels, alpha) = color_args(args)?;
+            let lightness = percent_or_number(channels[0], 1.0)?;
+            let a = percent_or_number(channels[1], 0.4)?;
+            let b = percent_or_number(channels[2], 0.4)?;
+            return Some(Self { space: InterpolationSpace::Oklab, components: [lightness, a, b], alpha });
+        }
+        if let Some(args) = function_args(&css, "oklch") {
+            let (channels, alpha) = color_args(args)?;
+            let lightness = percent_or_number(channels[0], 1.0)?;
+            let chroma = percent_or_number(channels[1], 0.4)?;
+            let hue = parse_angle(channels[2]).or_else(|| channels[2].parse().ok())?.to_radians();
+            let components = [lightness, chroma * hue.cos(), chroma * hue.sin()];
+            return Some(Self { space: InterpolationSpace::Oklab, components, alpha });
+        }
+        Color::from_name(&css).map(Self::from)
+    }
+    
+    fn srgb(components: [f64; 3], alpha: f64) -> Self {
+        Self { space: InterpolationSpace::Srgb, components: components.map(|c| c.clamp(0.0, 1.0)), alpha }
+    }
+    
+    /// Same color in Oklab
+    pub fn to_oklab(self) -> Self {
+        if self.space == InterpolationSpace::Oklab {
+            return self;
+        }
+        let [r, g, b] = self.components.map(srgb_to_linear);
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+        Self {
+            space: InterpolationSpace::Oklab,
+            components: [
+                0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+                1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+                0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+            ],
+            alpha: self.alpha,
+        }
+    }
+    
+    /// sRGB components from 0 to 1, gamut-clipped
+    pub fn to_srgb(self) -> [f64; 3] {
+        if self.space == InterpolationSpace::Srgb {
+            return self.components;
+        }
+        let [lightness, a, b] = self.components;
+        let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+        let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+        let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+        [
+            4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+            -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+            -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+        ].map(|c| linear_to_srgb(c).clamp(0.0, 1.0))
+    }
+    
+    pub fn to_color(self) -> Color {
+        let [r, g, b] = self.to_srgb().map(|c| (c * 255.0).round() as u8);
+        Color::rgba(r, g, b, (self.alpha.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+    
+    /// Interpolate with premultiplied alpha, in sRGB only when both colors
+    /// are legacy
+    pub fn interpolate(&self, to: &Self, t: f64) -> Self {
+        let (from, to) = if self.space == to.space {
+            (*self, *to)
+        } else {
+            (self.to_oklab(), to.to_oklab())
+        };
+        let alpha = lerp(from.alpha, to.alpha, t).clamp(0.0, 1.0);
+        let mut components = [0.0; 3];
+        for (i, component) in components.iter_mut().enumerate() {
+            let premultiplied = lerp(from.components[i] * from.alpha, to.components[i] * to.alpha, t);
+            *component = if alpha > 0.0 { premultiplied / alpha } else { 0.0 };
+        }
+        if from.space == InterpolationSpace::Srgb {
+            components = components.map(|c| c.clamp(0.0, 1.0));
+        }
+        Self { space: from.space, components, alpha }
+    }
+}
+
+impl fmt::Display for AnimatedColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.space {
+            InterpolationSpace::Srgb => {
+                let [r, g, b] = self.components.map(|c| (c * 255.0).round() as u8);
+                if self.alpha >= 1.0 {
+                    write!(f, "rgb({}, {}, {})", r, g, b)
+                } else {
+                    write!(f, "rgba({}, {}, {}, {})", r, g, b, number(self.alpha))
+                }
+            }
+            InterpolationSpace::Oklab => {
+                let [l, a, b] = self.components.map(number);
+                if self.alpha >= 1.0 {
+                    write!(f, "oklab({} {} {})", l, a, b)
+                } else {
+                    write!(f, "oklab({} {} {} / {})", l, a, b, number(self.alpha))
+                }
+            }
+        }
+    }
+}
+
+/// Three channels and the alpha of a color function, comma or space syntax
+fn color_args(args: &str) -> Option<(Vec<&str>, f64)> {
+    let (channels, alpha) = match args.split_once('/') {
+        Some((channels, alpha)) => (channels, Some(alpha.trim())),
+        None => (args, None),
+    };
+    let mut channels: Vec<&str> = channels.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let alpha = match (alpha, channels.len()) {
+        (Some(alpha), 3) => alpha,
+        (None, 4) => channels.pop()?,
+        (None, 3) => "1",
+        _ => return None,
+    };
+    Some((channels, percent_or_number(alpha, 1.0)?.clamp(0.0, 1.0)))
+}
+
+/// Number, or percentage of `full`
+fn percent_or_number(s: &str, full: f64) -> Option<f64> {
+    match s.strip_suffix('%') {
+        Some(p) => p.parse::<f64>().ok().map(|p| p / 100.0 * full),
+        None => s.parse().ok(),
+    }
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> [f64; 3] {
+    let channel = |n: f64| {
+        let k = (n + hue / 30.0).rem_euclid(12.0);
+        let a = saturation * lightness.min(1.0 - lightness);
+        lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    [channel(0.0), channel(8.0), channel(4.0)]
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+// ============================================================================
+// Transforms
+// ============================================================================
+
+/// 2D transform function; single-axis forms are normalized to two axes
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransformFunction {
+    Translate((f64, Unit), (f64, Unit)),
+    Scale(f64, f64),
+    /// Degrees
+    Rotate(f64),
+    /// Degrees on each axis
+    Skew(f64, f64),
+    Matrix([f64; 6]),
+}
+
+impl TransformFunction {
+    fn parse(css: &str) -> Option<Self> {
+        let (name, rest) = css.split_once('(')?;
+        let args: Vec<&str> = rest.strip_suffix(')')?.split(',').map(str::trim).collect();
+        let length = |s: &str| parse_length(s).filter(|(_, u)| matches!(u, Unit::Px | Unit::Percent | Unit::Em | Unit::Rem | Unit::None));
+        let zero = (0.0, Unit::Px);
+        Some(match (name.trim(), args.as_slice()) {
+            ("translate", [x]) => Self::Translate(length(x)?, zero),
+            ("translate", [x, y]) => Self::Translate(length(x)?, length(y)?),
+            ("translateX", [x]) => Self::Translate(length(x)?, zero),
+            ("translateY", [y]) => Self::Translate(zero, length(y)?),
+            ("scale", [s]) => { let s = s.parse().ok()?; Self::Scale(s, s) }
+            ("scale", [x, y]) => Self::Scale(x.parse().ok()?, y.parse().ok()?),
+            ("scaleX", [x]) => Self::Scale(x.parse().ok()?, 1.0),
+            ("scaleY", [y]) => Self::Scale(1.0, y.parse().ok()?),
+            ("rotate", [a]) => Self::Rotate(parse_angle(a)?),
+            ("skew", [x]) => Self::Skew(parse_angle(x)?, 0.0),
+            ("skew", [x, y]) => Self::Skew(parse_angle(x)?, parse_angle(y)?),
+            ("skewX", [x]) => Self::Skew(parse_angle(x)?, 0.0),
+            ("skewY", [y]) => Self::Skew(0.0, parse_angle(y)?),
+            ("matrix", [a, b, c, d, e, f]) => Self::Matrix([
+                a.parse().ok()?, b.parse().ok()?, c.parse().ok()?,
+                d.parse().ok()?, e.parse().ok()?, f.parse().ok()?,
+            ]),
+            _ => return None,
+        })
+    }
+    
+    /// Identity function of the same kind, for padding against `none`
+    fn identity(&self) -> Self {
+        match self {
+            Self::Translate(..) => Self::Translate((0.0, Unit::Px), (0.0, Unit::Px)),
+            Self::Scale(..) => Self::Scale(1.0, 1.0),
+            Self::Rotate(_) => Self::Rotate(0.0),
+            Self::Skew(..) => Self::Skew(0.0, 0.0),
+            Self::Matrix(_) => Self::Matrix(Matrix2D::IDENTITY.to_array()),
+        }
+    }
+    
+    fn interpolate(&self, to: &Self, t: f64) -> Option<Self> {
+        Some(match (self, to) {
+            (Self::Translate(ax, ay), Self::Translate(bx, by)) => {
+                let x = (lerp(ax.0, bx.0, t), common_unit(ax.0, ax.1, bx.0, bx.1)?);
+                let y = (lerp(ay.0, by.0, t), common_unit(ay.0, ay.1, by.0, by.1)?);
+                Self::Translate(x, y)
+            }
+            (Self::Scale(ax, ay), Self::Scale(bx, by)) => Self::Scale(lerp(*ax, *bx, t), lerp(*ay, *by, t)),
+            (Self::Rotate(a), Self::Rotate(b)) => Self::Rotate(lerp(*a, *b, t)),
+            (Self::Skew(ax, ay), Self::Skew(bx, by)) => Self::Skew(lerp(*ax, *bx, t), lerp(*ay, *by, t)),
+            (Self::Matrix(a), Self::Matrix(b)) => {
+                let from = Matrix2D::from_array(*a).decompose();
+                let to = Matrix2D::from_array(*b).decompose();
+                Self::Matrix(from.interpolate(&to, t).recompose().to_array())
+            }
+            _ => return None,
+        })
+    }
+    
+    /// Matrix of the function; `None` when a translation is not in pixels
+    fn to_matrix(&self) -> Option<Matrix2D> {
+        let px = |(value, unit): (f64, Unit)| match unit {
+            Unit::Px | Unit::None => Some(value),
+            _ => (value == 0.0).then_some(0.0),
+        };
+        Some(match self {
+            Self::Translate(x, y) => Matrix2D { e: px(*x)?, f: px(*y)?, ..Matrix2D::IDENTITY },
+            Self::Scale(x, y) => Matrix2D { a: *x, d: *y, ..Matrix2D::IDENTITY },
+            Self::Rotate(angle) => {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                Matrix2D { a: cos, b: sin, c: -sin, d: cos, ..Matrix2D::IDENTITY }
+            }
+            Self::Skew(x, y) => Matrix2D { b: y.to_radians().tan(), c: x.to_radians().tan(), ..Matrix2D::IDENTITY },
+            Self::Matrix(m) => Matrix2D::from_array(*m),
+        })
+    }
+}
+
+impl fmt::Display for TransformFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let length = |(value, unit): (f64, Unit)| format!("{}{}", number(value), unit_suffix(unit));
+        match self {
+            Self::Translate(x, y) => write!(f, "translate({}, {})", length(*x), length(*y)),
+            Self::Scale(x, y) => write!(f, "scale({}, {})", number(*x), number(*y)),
+            Self::Rotate(angle) => write!(f, "rotate({}deg)", number(*angle)),
+            Self::Skew(x, y) => write!(f, "skew({}deg, {}deg)", number(*x), number(*y)),
+            Self::Matrix(m) => write!(f, "matrix({}, {}, {}, {}, {}, {})",
+                number(m[0]), number(m[1]), number(m[2]), number(m[3]), number(m[4]), number(m[5])),
+        }
+    }
+}
+
+fn parse_transform_list(css: &str) -> Option<Vec<TransformFunction>> {
+    if css == "none" {
+        return Some(Vec::new());
+    }
+    tokens(css).into_iter().map(TransformFunction::parse).collect()
+}
+
+/// Interpolate transform lists (CSS Transforms 1, section 9)
+fn interpolate_transforms(from: &[TransformFunction], to: &[TransformFunction], t: f64) -> Option<Vec<TransformFunction>> {
+    // `none` against a list animates from the list's identity functions
+    let identities = |list: &[TransformFunction]| list.iter().map(TransformFunction::identity).collect::<Vec<_>>();
+    let (from, to) = match (from.is_empty(), to.is_empty()) {
+        (true, false) => (identities(to), to.to_vec()),
+        (false, true) => (from.to_vec(), identities(from)),
+        _ => (from.to_vec(), to.to_vec()),
+    };
+    
+    if from.len() == to.len() {
+        let pairwise: Option<Vec<_>> = from.iter().zip(&to).map(|(a, b)| a.interpolate(b, t)).collect();
+        if pairwise.is_some() {
+            return pairwise;
+        }
+    }
+    
+    // Lists that do not line up interpolate as decomposed matrices
+    let from = Matrix2D::from_array(transform_matrix(&from)?).decompose();
+    let to = Matrix2D::from_array(transform_matrix(&to)?).decompose();
+    Some(vec![TransformFunction::Matrix(from.interpolate(&to, t).recompose().to_array())])
+}
+
+/// Combined `matrix(a, b, c, d, e, f)` of a transform list; `None` when a
+/// translation is not in pixels
+pub fn transform_matrix(functions: &[TransformFunction]) -> Option<[f64; 6]> {
+    functions.iter()
+        .try_fold(Matrix2D::IDENTITY, |m, f| Some(m.multiply(&f.to_matrix()?)))
+        .map(Matrix2D::to_array)
+}
+
+/// 2D affine matrix, `matrix(a, b, c, d, e, f)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Matrix2D {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+    f: f64,
+}
+
+/// Matrix split into translation, scale, rotation and remaining skew
+#[derive(Debug, Clone, Copy)]
+struct Decomposed2D {
+    translate: [f64; 2],
+    scale: [f64; 2],
+    /// Degrees
+    angle: f64,
+    /// m11, m12, m21, m22
+    skew: [f64; 4],
+}
+
+impl Matrix2D {
+    const IDENTITY: Matrix2D = Matrix2D { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 0.0, f: 0.0 };
+    
+    fn from_array(m: [f64; 6]) -> Self {
+        Self { a: m[0], b: m[1], c: m[2], d: m[3], e: m[4], f: m[5] }
+    }
+    
+    fn to_array(self) -> [f64; 6] {
+        [self.a, self.b, self.c, self.d, self.e, self.f]
+    }
+    
+    /// `self` then `other`, as in a transform list
+    fn multiply(&self, other: &Self) -> Self {
+        Self {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+    
+    /// "Decomposing a 2D matrix" from CSS Transforms 1
+    fn decompose(&self) -> Decomposed2D {
+        let (mut row0x, mut row0y, mut row1x, mut row1y) = (self.a, self.b, self.c, self.d);
+        let mut scale = [row0x.hypot(row0y), row1x.hypot(row1y)];
+        
+        // A negative determinant flips one axis
+        if row0x * row1y - row0y * row1x < 0.0 {
+            if row0x < row1y { scale[0] = -scale[0]; } else { scale[1] = -scale[1]; }
+        }
+        if scale[0] != 0.0 { row0x /= scale[0]; row0y /= scale[0]; }
+        if scale[1] != 0.0 { row1x /= scale[1]; row1y /= scale[1]; }
+        
+        let angle = row0y.atan2(row0x);
+        if angle != 0.0 {
+            let (sn, cs) = (-row0y, row0x);
+            let (m11, m12, m21, m22) = (row0x, row0y, row1x, row1y);
+            row0x = cs * m11 + sn * m21;
+            row0y = cs * m12 + sn * m22;
+            row1x = -sn * m11 + cs * m21;
+            row1y = -sn * m12 + cs * m22;
+        }
+        
+        Decomposed2D {
+            translate: [self.e, self.f],
+            scale,
+            angle: angle.to_degrees(),
+            skew: [row0x, row0y, row1x, row1y],
+        }
+    }
+}
+
+impl Decomposed2D {
+    fn interpolate(&self, to: &Self, t: f64) -> Self {
+        let (mut from, mut to) = (*self, *to);
+        
+        // An x flip against a y flip is a half turn, not a squash through zero
+        if (from.scale[0] < 0.0 && to.scale[1] < 0.0) || (from.scale[1] < 0.0 && to.scale[0] < 0.0) {
+            from.scale = [-from.scale[0], -from.scale[1]];
+            from.angle += if from.angle < 0.0 { 180.0 } else { -180.0 };
+        }
+        // Rotate the short way round
+        if from.angle == 0.0 { from.angle = 360.0; }
+        if to.angle == 0.0 { to.angle = 360.0; }
+        if (from.angle - to.angle).abs() > 180.0 {
+            if from.angle > to.angle { from.angle -= 360.0; } else { to.angle -= 360.0; }
+        }
+        
+        let mix2 = |a: [f64; 2], b: [f64; 2]| [lerp(a[0], b[0], t), lerp(a[1], b[1], t)];
+        Self {
+            translate: mix2(from.translate, to.translate),
+            scale: mix2(from.scale, to.scale),
+            angle: lerp(from.angle, to.angle, t),
+            skew: [0, 1, 2, 3].map(|i| lerp(from.skew[i], to.skew[i], t)),
+        }
+    }
+    
+    /// "Recomposing to a 2D matrix" from CSS Transforms 1
+    fn recompose(&self) -> Matrix2D {
+        let [m11, m12, m21, m22] = self.skew;
+        let [tx, ty] = self.translate;
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let [sx, sy] = self.scale;
+        Matrix2D {
+            a: (cos * m11 + sin * m21) * sx,
+            b: (cos * m12 + sin * m22) * sx,
+            c: (-sin * m11 + cos * m21) * sy,
+            d: (-sin * m12 + cos * m22) * sy,
+            e: tx * m11 + ty * m21,
+            f: tx * m12 + ty * m22,
+        }
+    }
+}
+
+// ============================================================================
+// Shadows
+// ============================================================================
+
+/// One `box-shadow` or `text-shadow` layer, lengths in pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shadow {
+    pub inset: bool,
+    pub x: f64,
+    pub y: f64,
+    pub blur: f64,
+    pub spread: f64,
+    pub color: AnimatedColor,
+}
+
+impl Shadow {
+    fn parse(css: &str) -> Option<Self> {
+        let mut inset = false;
+        let mut lengths = Vec::new();
+        // currentcolor is not resolved here; black is its initial value
+        let mut color = AnimatedColor::from(Color::BLACK);
+        for token in tokens(css) {
+            if token == "inset" {
+                inset = true;
+            } else if let Some((value, Unit::Px | Unit::None)) = parse_length(token) {
+                lengths.push(value);
+            } else {
+                color = AnimatedColor::parse(token)?;
+            }
+        }
+        if !(2..=4).contains(&lengths.len()) {
+            return None;
+        }
+        lengths.resize(4, 0.0);
+        Some(Self { inset, x: lengths[0], y: lengths[1], blur: lengths[2], spread: lengths[3], color })
+    }
+    
+    /// Transparent zero shadow padding a shorter list
+    fn padding(inset: bool) -> Self {
+        Self { inset, x: 0.0, y: 0.0, blur: 0.0, spread: 0.0, color: AnimatedColor::from(Color::TRANSPARENT) }
+    }
+}
+
+impl fmt::Display for Shadow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.inset { f.write_str("inset ")?; }
+        write!(f, "{}px {}px {}px {}px {}", number(self.x), number(self.y), number(self.blur), number(self.spread), self.color)
+    }
+}
+
+fn parse_shadows(css: &str) -> Option<Vec<Shadow>> {
+    if css == "none" {
+        return Some(Vec::new());
+    }
+    split_top_level(css, ',').into_iter().map(Shadow::parse).collect()
+}
+
+fn interpolate_shadows(from: &[Shadow], to: &[Shadow], t: f64) -> Option<Vec<Shadow>> {
+    (0..from.len().max(to.len())).map(|i| {
+        let a = from.get(i).cloned().unwrap_or_else(|| Shadow::padding(to[i].inset));
+        let b = to.get(i).cloned().unwrap_or_else(|| Shadow::padding(a.inset));
+        // Inset and outset shadows do not interpolate
+        (a.inset == b.inset).then(|| Shadow {
+            inset: a.inset,
+            x: lerp(a.x, b.x, t),
+            y: lerp(a.y, b.y, t),
+            blur: lerp(a.blur, b.blur, t).max(0.0),
+            spread: lerp(a.spread, b.spread, t),
+            color: a.color.interpolate(&b.color, t),
+        })
+    }).collect()
+}
+
+// ============================================================================
+// Gradients
+// ============================================================================
+
+/// Gradient shape
+#[derive(Debug, Clone, PartialEq)]
+pub enum GradientKind {
+    /// Direction in degrees
+    Linear(f64),
+    /// Shape, size and position, as written
+    Radial(String),
+}
+
+/// Color stop with an optional position
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorStop {
+    pub color: AnimatedColor,
+    pub position: Option<(f64, Unit)>,
+}
+
+/// Linear or radial gradient
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub repeating: bool,
+    pub stops: Vec<ColorStop>,
+}
+
+impl Gradient {
+    pub fn parse(css: &str) -> Option<Self> {
+        let (repeating, css) = match css.strip_prefix("repeating-") {
+            Some(rest) => (true, rest),
+            None => (false, css),
+        };
+        let (linear, args) = match function_args(css, "linear-gradient") {
+            Some(args) => (true, args),
+            None => (false, function_args(css, "radial-gradient")?),
+        };
+        let mut args = split_top_level(args, ',');
+        
+        let first = *args.first()?;
+        let kind = if linear {
+            match gradient_direction(first) {
+                Some(angle) => { args.remove(0); GradientKind::Linear(angle) }
+                None => GradientKind::Linear(180.0),
+            }
+        } else if ColorStop::parse(first).is_none() {
+            args.remove(0);
+            GradientKind::Radial(first.to_string())
+        } else {
+            GradientKind::Radial(String::new())
+        };
+        
+        let stops: Vec<ColorStop> = args.into_iter().map(ColorStop::parse).collect::<Option<_>>()?;
+        (stops.len() >= 2).then_some(Self { kind, repeating, stops })
+    }
+    
+    fn interpolate(&self, to: &Self, t: f64) -> Option<Self> {
+        if self.repeating != to.repeating || self.stops.len() != to.stops.len() {
+            return None;
+        }
+        let kind = match (&self.kind, &to.kind) {
+            (GradientKind::Linear(a), GradientKind::Linear(b)) => GradientKind::Linear(lerp(*a, *b, t)),
+            (GradientKind::Radial(a), GradientKind::Radial(b)) if a == b => self.kind.clone(),
+            _ => return None,
+        };
+        let stops = self.stops.iter().zip(&to.stops).map(|(a, b)| {
+            let position = match (a.position, b.position) {
+                (Some(pa), Some(pb)) => Some((lerp(pa.0, pb.0, t), common_unit(pa.0, pa.1, pb.0, pb.1)?)),
+                (None, None) => None,
+                _ => return None,
+            };
+            Some(ColorStop { color: a.color.interpolate(&b.color, t), position })
+        }).collect::<Option<_>>()?;
+        Some(Self { kind, repeating: self.repeating, stops })
+    }
+}
+
+impl ColorStop {
+    fn parse(css: &str) -> Option<Self> {
+        match tokens(css).as_slice() {
+            [color] => Some(Self { color: AnimatedColor::parse(color)?, position: None }),
+            [color, position] => Some(Self { color: AnimatedColor::parse(color)?, position: Some(parse_length(position)?) }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Gradient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.repeating { f.write_str("repeating-")?; }
+        match &self.kind {
+            GradientKind::Linear(angle) => write!(f, "linear-gradient({}deg", number(*angle))?,
+            GradientKind::Radial(shape) if shape.is_empty() => f.write_str("radial-gradient(")?,
+            GradientKind::Radial(shape) => write!(f, "radial-gradient({}", shape)?,
+        }
+        for (i, stop) in self.stops.iter().enumerate() {
+            let leading = matches!(&self.kind, GradientKind::Radial(shape) if shape.is_empty()) && i == 0;
+            if !leading { f.write_str(", ")?; }
+            write!(f, "{}", stop.color)?;
+            if let Some((value, unit)) = stop.position {
+                write!(f, " {}{}", number(value), unit_suffix(unit))?;
+            }
+        }
+        f.write_str(")")
+    }
+}
+
+/// Angle of a linear gradient's first argument, if it is a direction
+fn gradient_direction(css: &str) -> Option<f64> {
+    if let Some(side) = css.strip_prefix("to ") {
+        let mut sides: Vec<&str> = side.split_whitespace().collect();
+        sides.sort_unstable();
+        return Some(match sides.as_slice() {
+            ["top"] => 0.0,
+            ["right"] => 90.0,
+            ["bottom"] => 180.0,
+            ["left"] => 270.0,
+            ["right", "top"] => 45.0,
+            ["bottom", "right"] => 135.0,
+            ["bottom", "left"] => 225.0,
+            ["left", "top"] => 315.0,
+            _ => return None,
+        });
+    }
+    parse_angle(css)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn mix(property: &str, from: &str, to: &str, t: f64) -> String {
+        let from = AnimatableValue::parse(property, from);
+        let to = AnimatableValue::parse(property, to);
+        interpolate_property(property, &from, &to, t).to_string()
+    }
+    
+    #[test]
+    fn test_color_spaces() {
+        // Legacy colors mix in premultiplied sRGB, so the red does not darken
+        assert_eq!(mix("color", "red", "transparent", 0.5), "rgba(255, 0, 0, 0.5)");
+        assert_eq!(mix("color", "#000", "rgb(255 255 255 / 50%)", 0.5), "rgba(85, 85, 85, 0.75)");
+        
+        // Any modern color moves the mix to Oklab
+        let oklab = mix("color", "oklab(0.2 0 0)", "oklch(60% 0 0)", 0.5);
+        assert_eq!(oklab, "oklab(0.4 0 0)");
+        let white = AnimatedColor::parse("white").unwrap().to_oklab();
+        assert!((white.components[0] - 1.0).abs() < 1e-3);
+        assert_eq!(AnimatedColor::parse("oklab(1 0 0)").unwrap().to_color().r, 255);
+    }
+    
+    #[test]
+    fn test_transform_interpolation() {
+        // Matching lists interpolate per function, `none` as identities
+        assert_eq!(mix("transform", "translateX(10px) rotate(0deg)", "translate(30px, 10px) rotate(90deg)", 0.5),
+            "translate(20px, 5px) rotate(45deg)");
+        assert_eq!(mix("transform", "none", "scale(3)", 0.5), "scale(2, 2)");
+        
+        // Mismatched lists go through matrix decomposition
+        let value = AnimatableValue::parse("transform", "rotate(0deg)")
+            .interpolate(&AnimatableValue::parse("transform", "scale(2) rotate(90deg)"), 0.5)
+            .unwrap();
+        let AnimatableValue::Transform(functions) = value else { panic!() };
+        let TransformFunction::Matrix(m) = functions[0] else { panic!() };
+        let (sin, cos) = 45f64.to_radians().sin_cos();
+        let expected = [1.5 * cos, 1.5 * sin, -1.5 * sin, 1.5 * cos, 0.0, 0.0];
+        assert!(m.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-9), "{:?}", m);
+    }
+    
+    #[test]
+    fn test_shadows_gradients_and_discrete() {
+        assert_eq!(mix("box-shadow", "none", "2px 4px 6px red", 0.5), "1px 2px 3px 0px rgba(255, 0, 0, 0.5)");
+        assert!(!AnimatableValue::parse("box-shadow", "inset 1px 1px red")
+            .is_interpolable_with(&AnimatableValue::parse("box-shadow", "1px 1px red")));
+        
+        assert_eq!(mix("background-image", "linear-gradient(to right, red, blue 50%)",
+            "linear-gradient(180deg, blue, blue 100%)", 0.5),
+            "linear-gradient(135deg, rgb(128, 0, 128), rgb(0, 0, 255) 75%)");
+        
+        assert_eq!(mix("padding", "10px 20px", "20px 40px", 0.5), "15px 30px");
+        assert_eq!(mix("z-index", "1", "4", 0.4), "2");
+        assert_eq!(mix("position", "static", "absolute", 0.49), "static");
+        assert_eq!(mix("position", "static", "absolute", 0.5), "absolute");
+        assert_eq!(mix("visibility", "hidden", "visible", 0.1), "visible");
+        assert_eq!(mix("display", "block", "none", 0.9), "block");
+        assert_eq!(mix("display", "block", "none", 1.0), "none");
+    }
+}
@@ -26,11 +26,14 @@ pub mod rule_tree;
 pub mod inheritance;
 pub mod selector_opt;
 pub mod transitions;
+pub mod interpolation;
 pub mod style_sharing;
 pub mod selector_match_cache;
 pub mod parallel_css_parser;
 pub mod parallel_style;
 pub mod subtree_isolation;
+pub mod viewport;
+pub mod media;
 
 // Phase 1: Selector Performance
 pub mod selector_bloom;
@@ -51,10 +54,12 @@ pub mod view_transitions;
 pub mod predictive;
 
 pub use parser::CssParser;
-pub use cascade::StyleResolver;
+pub use cascade::{StyleResolver, StyleTrace, MatchedRuleTrace, TracedDeclaration, RuleOrigin, RuleUsage};
 pub use properties::{PropertyId, PropertyValue};
-pub use computed::ComputedStyle;
+pub use computed::{ComputedStyle, AccentColor, Appearance, ColorScheme};
 pub use computed::PropertyMask;
+pub use viewport::{Viewport, ViewportChange};
+pub use media::MediaEnvironment;
 pub use variables::{
     VariableScope, CustomPropertyValue, ResolvedValue,
     CalcExpression, css_min, css_max, css_clamp,
@@ -72,13 +77,14 @@ pub use style_cache::{StyleCache, StyleCacheKey, SharedStyle, CacheStats};
 pub use container::{ContainerContext, ContainerQuery, ContainerRegistry};
 pub use mask::{Mask, MaskLayer, MaskImage, Isolation, MaskComposite, MaskMode};
 pub use web_animations::{
-    Animation, AnimationEffect, Keyframe, PlayState, DocumentAnimations,
+    Animation, AnimationClass, AnimationEffect, Keyframe, PlayState, DocumentAnimations, ComputedTiming,
     Fixed16 as AnimationFixed16, DeterministicTiming,
     // Phase 4: Animation performance
     KeyframePrecompute, PrecomputedAnimation, PrecomputedValue,
     CompositorAnimation, CompositorProperty, CompositorValue,
     CompositorAnimationController, CompositorAnimationState,
 };
+pub use parallel_css_parser::{KeyframesRule, KeyframeSelector};
 pub use rule_tree::{RuleTree, RuleNode, PackedValue, ColorInterner, RuleSpecificity, CascadeLevel};
 pub use inheritance::{InheritanceSnapshot, InheritedProperties, CustomPropertyResolver, OnDemandStyler};
 pub use selector_opt::{SelectorIndex, RtlMatcher, HybridSelector, CompiledSelector};
@@ -86,6 +92,10 @@ pub use transitions::{
     Transition, ActiveTransition, TransitionEngine,
     TimingFunction, StepPosition, Fixed16 as TransitionFixed16,
 };
+pub use interpolation::{
+    AnimatableValue, AnimationType, AnimatedColor, InterpolationSpace,
+    TransformFunction, Shadow, Gradient, GradientKind, ColorStop, interpolate_property,
+};
 pub use style_sharing::{
     StyleSharingCache, StyleKey, SharedStyleRef, SharingStats,
     StyleBloomKey, StyleHasher, ElementContext as SharingElementContext,
@@ -109,7 +119,7 @@ pub use selector_split::{
 pub use cow_style::{
     CowInheritedProps, FlatCustomProperties,
     FontStyle as CowFontStyle, LineHeight as CowLineHeight,
-    TextAlign as CowTextAlign, Color as CowColor,
+    TextAlign as CowTextAlign, Color as CowColor, Hyphens,
 };
 
 // Phase 3 exports
@@ -186,6 +196,9 @@ impl Stylesheet {
 pub struct Rule {
     pub selectors: Vec<Selector>,
     pub declarations: Vec<Declaration>,
+    /// Byte range of the rule in the stylesheet text; `None` for built-in
+    /// rules
+    pub source_range: Option<std::ops::Range<usize>>,
 }
 
 /// CSS selector with parsed components
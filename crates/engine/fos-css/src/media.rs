@@ -0,0 +1,171 @@
+//! Media Queries
+//!
+//! Evaluation of `@media` conditions against the environment a page is
+//! rendered in: the media type, the viewport size and the user's
+//! preferences for color scheme, motion and data usage.
+
+/// What media queries are evaluated against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaEnvironment {
+    /// Viewport width in CSS pixels
+    pub width: f32,
+    /// Viewport height in CSS pixels
+    pub height: f32,
+    /// prefers-color-scheme: dark
+    pub prefers_dark: bool,
+    /// prefers-reduced-motion: reduce
+    pub reduced_motion: bool,
+    /// prefers-reduced-data: reduce, set while the data saver is on
+    pub reduced_data: bool,
+    /// Device pixels per CSS pixel, including page zoom
+    pub resolution: f32,
+    /// Laid out for paged print output: `print` matches, `screen` doesn't
+    pub print: bool,
+}
+
+impl Default for MediaEnvironment {
+    fn default() -> Self {
+        Self::new(0.0, 0.0)
+    }
+}
+
+impl MediaEnvironment {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            prefers_dark: false,
+            reduced_motion: false,
+            reduced_data: false,
+            resolution: 1.0,
+            print: false,
+        }
+    }
+
+    /// Whether a comma-separated media query list matches; an empty list
+    /// matches everything
+    pub fn matches(&self, query_list: &str) -> bool {
+        let query_list = query_list.trim();
+        query_list.is_empty() || query_list.split(',').any(|query| self.matches_query(query))
+    }
+
+    /// `[not | only] [type] [and (feature)]*`; unknown types and features
+    /// never match
+    fn matches_query(&self, query: &str) -> bool {
+        let query = query.trim().to_ascii_lowercase();
+        let (negated, query) = match query.strip_prefix("not ") {
+            Some(rest) => (true, rest),
+            None => (false, query.strip_prefix("only ").unwrap_or(&query)),
+        };
+
+        let matched = query.split(" and ").all(|part| {
+            let part = part.trim();
+            match part.strip_prefix('(').and_then(|p| p.strip_suffix(')')) {
+                Some(feature) => self.matches_feature(feature),
+                None => match part {
+                    "all" => true,
+                    "screen" => !self.print,
+                    "print" => self.print,
+                    _ => false,
+                },
+            }
+        });
+        matched != negated
+    }
+
+    fn matches_feature(&self, feature: &str) -> bool {
+        let (name, value) = match feature.split_once(':') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (feature.trim(), None),
+        };
+
+        match (name, value) {
+            ("prefers-reduced-data", None | Some("reduce")) => self.reduced_data,
+            ("prefers-reduced-data", Some("no-preference")) => !self.reduced_data,
+            ("prefers-reduced-motion", None | Some("reduce")) => self.reduced_motion,
+            ("prefers-reduced-motion", Some("no-preference")) => !self.reduced_motion,
+            ("prefers-color-scheme", Some("dark")) => self.prefers_dark,
+            ("prefers-color-scheme", Some("light")) => !self.prefers_dark,
+            ("orientation", Some("portrait")) => self.height >= self.width,
+            ("orientation", Some("landscape")) => self.width > self.height,
+            ("width", Some(v)) => length_px(v).is_some_and(|px| self.width == px),
+            ("min-width", Some(v)) => length_px(v).is_some_and(|px| self.width >= px),
+            ("max-width", Some(v)) => length_px(v).is_some_and(|px| self.width <= px),
+            ("height", Some(v)) => length_px(v).is_some_and(|px| self.height == px),
+            ("min-height", Some(v)) => length_px(v).is_some_and(|px| self.height >= px),
+            ("max-height", Some(v)) => length_px(v).is_some_and(|px| self.height <= px),
+            ("resolution", Some(v)) => resolution_dppx(v).is_some_and(|dppx| self.resolution == dppx),
+            ("min-resolution", Some(v)) => resolution_dppx(v).is_some_and(|dppx| self.resolution >= dppx),
+            ("max-resolution", Some(v)) => resolution_dppx(v).is_some_and(|dppx| self.resolution <= dppx),
+            ("-webkit-device-pixel-ratio", Some(v)) => v.parse::<f32>().is_ok_and(|ratio| self.resolution == ratio),
+            ("-webkit-min-device-pixel-ratio", Some(v)) => v.parse::<f32>().is_ok_and(|ratio| self.resolution >= ratio),
+            ("-webkit-max-device-pixel-ratio", Some(v)) => v.parse::<f32>().is_ok_and(|ratio| self.resolution <= ratio),
+            _ => false,
+        }
+    }
+}
+
+/// A media feature length in pixels; em and rem use the 16px initial
+/// font size, as media queries do
+fn length_px(value: &str) -> Option<f32> {
+    let (number, scale) = if let Some(n) = value.strip_suffix("px") {
+        (n, 1.0)
+    } else if let Some(n) = value.strip_suffix("rem").or_else(|| value.strip_suffix("em")) {
+        (n, 16.0)
+    } else if value == "0" {
+        (value, 1.0)
+    } else {
+        return None;
+    };
+    number.trim().parse::<f32>().ok().map(|n| n * scale)
+}
+
+/// A media feature resolution in dots per CSS pixel
+fn resolution_dppx(value: &str) -> Option<f32> {
+    let (number, per_dppx) = if let Some(n) = value.strip_suffix("dppx") {
+        (n, 1.0)
+    } else if let Some(n) = value.strip_suffix("dpi") {
+        (n, 96.0)
+    } else if let Some(n) = value.strip_suffix("dpcm") {
+        (n, 96.0 / 2.54)
+    } else if let Some(n) = value.strip_suffix('x') {
+        (n, 1.0)
+    } else {
+        return None;
+    };
+    number.trim().parse::<f32>().ok().map(|n| n / per_dppx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_queries() {
+        let mut env = MediaEnvironment::new(800.0, 600.0);
+        assert!(env.matches(""));
+        assert!(env.matches("screen and (min-width: 600px)"));
+        assert!(!env.matches("print"));
+        assert!(env.matches("print, (max-width: 50em)"));
+        assert!(env.matches("not all and (orientation: portrait)"));
+
+        env.print = true;
+        assert!(env.matches("print"));
+        assert!(!env.matches("screen and (min-width: 600px)"));
+        assert!(env.matches("not screen"));
+        env.print = false;
+
+        assert!(env.matches("(prefers-reduced-data: no-preference)"));
+        assert!(!env.matches("(prefers-reduced-data)"));
+        env.reduced_data = true;
+        assert!(env.matches("(prefers-reduced-data: reduce)"));
+        assert!(env.matches("(prefers-reduced-data)"));
+        assert!(!env.matches("(unknown-feature: 1)"));
+
+        env.resolution = 2.0;
+        assert!(env.matches("(min-resolution: 2dppx)"));
+        assert!(env.matches("(min-resolution: 192dpi) and (max-resolution: 2x)"));
+        assert!(env.matches("(-webkit-min-device-pixel-ratio: 1.5)"));
+        assert!(!env.matches("(min-resolution: 3x)"));
+    }
+}
@@ -117,22 +117,51 @@ pub struct KeyframesRule {
 }
 
 /// A single keyframe
+///
+/// A block with a selector list such as `0%, 50%` becomes one keyframe per
+/// selector.
 #[derive(Debug, Clone)]
 pub struct Keyframe {
     /// Percentage (0-100) or 'from'/'to'
     pub selector: KeyframeSelector,
-    /// Declarations
+    /// Declarations, without `!important` ones and `animation-*` properties
     pub declarations: Vec<Declaration>,
+    /// `animation-timing-function` used from this keyframe to the next
+    pub easing: Option<String>,
 }
 
 /// Keyframe selector
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum KeyframeSelector {
     From,
     To,
     Percentage(f32),
 }
 
+impl KeyframeSelector {
+    /// Parse `from`, `to` or a percentage between 0% and 100%
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("from") {
+            return Some(Self::From);
+        }
+        if text.eq_ignore_ascii_case("to") {
+            return Some(Self::To);
+        }
+        let pct: f32 = text.strip_suffix('%')?.parse().ok()?;
+        (0.0..=100.0).contains(&pct).then_some(Self::Percentage(pct))
+    }
+    
+    /// Offset in the animation, 0 to 1
+    pub fn offset(&self) -> f64 {
+        match self {
+            Self::From => 0.0,
+            Self::To => 1.0,
+            Self::Percentage(pct) => *pct as f64 / 100.0,
+        }
+    }
+}
+
 /// @supports rule
 #[derive(Debug, Clone)]
 pub struct SupportsRule {
@@ -573,7 +602,11 @@ impl<'a> CssParser<'a> {
     
     fn parse_keyframes_rule(&mut self) -> Result<Rule, CssParseError> {
         self.skip_whitespace();
-        let name = self.consume_ident();
+        let name = if matches!(self.current(), Some('"' | '\'')) {
+            self.consume_string()?
+        } else {
+            self.consume_ident()
+        };
         self.skip_whitespace();
         
         if self.current() != Some('{') {
@@ -591,23 +624,30 @@ impl<'a> CssParser<'a> {
                 break;
             }
             
-            // Parse keyframe selector
-            let selector_text = self.consume_until('{').trim().to_string();
-            let selector = if selector_text == "from" {
-                KeyframeSelector::From
-            } else if selector_text == "to" {
-                KeyframeSelector::To
-            } else {
-                let pct = selector_text.trim_end_matches('%').parse().unwrap_or(0.0);
-                KeyframeSelector::Percentage(pct)
-            };
+            // Parse keyframe selector list; one invalid selector drops the block
+            let selector_text = self.consume_until('{');
+            let selectors: Option<Vec<_>> = selector_text.split(',').map(KeyframeSelector::parse).collect();
             
             if self.current() == Some('{') {
                 self.advance();
             }
             
-            let declarations = self.parse_declarations()?;
-            keyframes.push(Keyframe { selector, declarations });
+            let mut easing = None;
+            let mut declarations = Vec::new();
+            for decl in self.parse_declarations()? {
+                if decl.important {
+                    continue;
+                }
+                if decl.property == "animation-timing-function" {
+                    easing = Some(decl.value);
+                } else if !decl.property.starts_with("animation") {
+                    declarations.push(decl);
+                }
+            }
+            
+            for selector in selectors.into_iter().flatten() {
+                keyframes.push(Keyframe { selector, declarations: declarations.clone(), easing: easing.clone() });
+            }
         }
         
         Ok(Rule::Keyframes(KeyframesRule { name, keyframes }))
@@ -907,6 +947,16 @@ mod tests {
             assert_eq!(rule.name, "fade");
             assert_eq!(rule.keyframes.len(), 2);
         }
+        
+        let css = "@keyframes \"slide in\" { 0%, 50% { left: 0 !important; top: 1px; animation-timing-function: ease-in; animation-delay: 1s } 120% { top: 9px } TO { top: 2px } }";
+        let stylesheet = parse_stylesheet("test.css", css);
+        let Rule::Keyframes(rule) = &stylesheet.rules[0] else { panic!("expected @keyframes") };
+        assert_eq!(rule.name, "slide in");
+        let offsets: Vec<f64> = rule.keyframes.iter().map(|k| k.selector.offset()).collect();
+        assert_eq!(offsets, vec![0.0, 0.5, 1.0]);
+        assert_eq!(rule.keyframes[1].declarations.len(), 1);
+        assert_eq!(rule.keyframes[1].declarations[0].property, "top");
+        assert_eq!(rule.keyframes[1].easing.as_deref(), Some("ease-in"));
     }
     
     #[test]
@@ -3,6 +3,7 @@
 //! Parses CSS stylesheets into our internal representation.
 
 use crate::{Stylesheet, Rule, Selector, Declaration, Specificity, CssError};
+use crate::media::MediaEnvironment;
 use crate::properties::{PropertyId, PropertyValue, Keyword, Length, LengthUnit, Color};
 
 /// CSS Parser
@@ -13,8 +14,18 @@ impl CssParser {
         Self
     }
     
-    /// Parse a CSS stylesheet
+    /// Parse a CSS stylesheet, skipping `@media` rules
     pub fn parse(&self, css: &str) -> Result<Stylesheet, CssError> {
+        self.parse_in(css, None)
+    }
+    
+    /// Parse a CSS stylesheet, keeping the rules of `@media` blocks that
+    /// match `media`
+    pub fn parse_with_media(&self, css: &str, media: &MediaEnvironment) -> Result<Stylesheet, CssError> {
+        self.parse_in(css, Some(media))
+    }
+    
+    fn parse_in(&self, css: &str, media: Option<&MediaEnvironment>) -> Result<Stylesheet, CssError> {
         use lightningcss::stylesheet::{StyleSheet, ParserOptions};
         
         let options = ParserOptions::default();
@@ -28,27 +39,43 @@ impl CssParser {
         let mut result = Stylesheet::new();
         
         // Convert lightningcss rules to our format
-        for rule in stylesheet.rules.0.iter() {
-            if let Some(converted) = self.convert_rule(rule) {
-                result.rules.push(converted);
-            }
-        }
+        self.convert_rules(css, &stylesheet.rules, media, &mut result.rules);
         
         Ok(result)
     }
     
-    fn convert_rule(&self, rule: &lightningcss::rules::CssRule) -> Option<Rule> {
+    fn convert_rules(
+        &self,
+        css: &str,
+        rules: &lightningcss::rules::CssRuleList,
+        media: Option<&MediaEnvironment>,
+        out: &mut Vec<Rule>,
+    ) {
         use lightningcss::rules::CssRule;
         
-        match rule {
-            CssRule::Style(style_rule) => {
-                let selectors = self.convert_selectors(&style_rule.selectors);
-                let declarations = self.convert_declarations(&style_rule.declarations);
-                
-                Some(Rule { selectors, declarations })
+        for rule in rules.0.iter() {
+            match rule {
+                CssRule::Style(style_rule) => {
+                    let selectors = self.convert_selectors(&style_rule.selectors);
+                    let declarations = self.convert_declarations(&style_rule.declarations);
+                    let source_range = rule_range(css, style_rule.loc.line, style_rule.loc.column);
+                    
+                    out.push(Rule { selectors, declarations, source_range });
+                }
+                CssRule::Media(media_rule) => {
+                    let Some(env) = media else { continue };
+                    // Evaluate the query as written rather than as reserialized
+                    let query = rule_range(css, media_rule.loc.line, media_rule.loc.column)
+                        .and_then(|range| css[range].split('{').next().map(str::to_string))
+                        .unwrap_or_default();
+                    let query = query.trim_start().strip_prefix("@media").unwrap_or(&query);
+                    if env.matches(query) {
+                        self.convert_rules(css, &media_rule.rules, media, out);
+                    }
+                }
+                // Skip other rule types for now (keyframes, etc.)
+                _ => {}
             }
-            // Skip other rule types for now (media queries, keyframes, etc.)
-            _ => None,
         }
     }
     
@@ -67,21 +94,78 @@ impl CssParser {
         
         // Process declarations
         for decl in declarations.declarations.iter() {
-            if let Some(converted) = self.convert_declaration(decl, false) {
-                result.push(converted);
-            }
+            self.push_declaration(decl, false, &mut result);
         }
         
         // Process important declarations
         for decl in declarations.important_declarations.iter() {
-            if let Some(converted) = self.convert_declaration(decl, true) {
-                result.push(converted);
-            }
+            self.push_declaration(decl, true, &mut result);
         }
         
         result
     }
     
+    /// Convert a declaration, expanding shorthands into their longhands
+    fn push_declaration(&self, decl: &lightningcss::properties::Property, important: bool, out: &mut Vec<Declaration>) {
+        use lightningcss::properties::Property;
+        
+        match decl {
+            Property::Font(font) => out.extend(self.expand_font(font, important)),
+            Property::Unparsed(unparsed) if unparsed.property_id.name() == "font" => {
+                match self.system_font(&unparsed.value) {
+                    Some(font) => out.extend(self.expand_font(&font, important)),
+                    // var() and friends stay raw
+                    None => out.extend(self.convert_declaration(decl, important)),
+                }
+            }
+            _ => out.extend(self.convert_declaration(decl, important)),
+        }
+    }
+    
+    /// Expand the `font` shorthand; omitted parts were reset to their
+    /// initial values by lightningcss
+    fn expand_font(&self, font: &lightningcss::properties::font::Font, important: bool) -> Vec<Declaration> {
+        [
+            (PropertyId::FontStyle, Some(self.convert_font_style(&font.style))),
+            (PropertyId::FontVariant, self.convert_font_variant(&font.variant_caps)),
+            (PropertyId::FontWeight, Some(self.convert_font_weight(&font.weight))),
+            (PropertyId::FontStretch, Some(self.convert_font_stretch(&font.stretch))),
+            (PropertyId::FontSize, self.convert_font_size(&font.size)),
+            (PropertyId::LineHeight, self.convert_line_height(&font.line_height)),
+            (PropertyId::FontFamily, Some(self.convert_font_family(&font.family))),
+        ]
+        .into_iter()
+        .filter_map(|(property, value)| Some(Declaration { property, value: value?, important }))
+        .collect()
+    }
+    
+    /// `font: caption` and the other system font keywords, which lightningcss
+    /// leaves unparsed
+    fn system_font(&self, value: &lightningcss::properties::custom::TokenList) -> Option<lightningcss::properties::font::Font<'static>> {
+        use lightningcss::properties::custom::{Token, TokenOrValue};
+        use lightningcss::properties::font::{Font, FontFamily, FontSize, GenericFontFamily};
+        use lightningcss::values::length::LengthPercentage;
+        
+        let mut tokens = value.0.iter().filter(|t| !matches!(t, TokenOrValue::Token(Token::WhiteSpace(_))));
+        let (Some(TokenOrValue::Token(Token::Ident(name))), None) = (tokens.next(), tokens.next()) else {
+            return None;
+        };
+        let size = match name.to_ascii_lowercase().as_str() {
+            "caption" | "icon" | "menu" | "message-box" => 13.0,
+            "small-caption" | "status-bar" => 11.0,
+            _ => return None,
+        };
+        Some(Font {
+            family: vec![FontFamily::Generic(GenericFontFamily::SystemUI)],
+            size: FontSize::Length(LengthPercentage::px(size)),
+            style: Default::default(),
+            weight: Default::default(),
+            stretch: Default::default(),
+            line_height: Default::default(),
+            variant_caps: Default::default(),
+        })
+    }
+    
     fn convert_declaration(&self, decl: &lightningcss::properties::Property, important: bool) -> Option<Declaration> {
         use lightningcss::properties::Property;
         
@@ -139,6 +223,68 @@ impl CssParser {
                     None
                 }
             }
+            Property::FontFamily(families) => Some(Declaration {
+                property: PropertyId::FontFamily,
+                value: self.convert_font_family(families),
+                important,
+            }),
+            Property::FontSize(size) => Some(Declaration {
+                property: PropertyId::FontSize,
+                value: self.convert_font_size(size)?,
+                important,
+            }),
+            Property::FontWeight(weight) => Some(Declaration {
+                property: PropertyId::FontWeight,
+                value: self.convert_font_weight(weight),
+                important,
+            }),
+            Property::FontStyle(style) => Some(Declaration {
+                property: PropertyId::FontStyle,
+                value: self.convert_font_style(style),
+                important,
+            }),
+            Property::FontStretch(stretch) => Some(Declaration {
+                property: PropertyId::FontStretch,
+                value: self.convert_font_stretch(stretch),
+                important,
+            }),
+            Property::FontVariantCaps(caps) => Some(Declaration {
+                property: PropertyId::FontVariant,
+                value: self.convert_font_variant(caps)?,
+                important,
+            }),
+            Property::LineHeight(line_height) => Some(Declaration {
+                property: PropertyId::LineHeight,
+                value: self.convert_line_height(line_height)?,
+                important,
+            }),
+            Property::AccentColor(accent) => {
+                use lightningcss::properties::ui::ColorOrAuto;
+                let value = match accent {
+                    ColorOrAuto::Auto => PropertyValue::Keyword(Keyword::Auto),
+                    ColorOrAuto::Color(color) => PropertyValue::Color(self.convert_color(color)?),
+                };
+                Some(Declaration { property: PropertyId::AccentColor, value, important })
+            }
+            Property::ColorScheme(scheme) => Some(Declaration {
+                property: PropertyId::ColorScheme,
+                value: self.convert_color_scheme(scheme),
+                important,
+            }),
+            Property::Appearance(appearance, _) => Some(Declaration {
+                property: PropertyId::Appearance,
+                value: self.convert_appearance(appearance)?,
+                important,
+            }),
+            Property::Hyphens(hyphens, _) => {
+                use lightningcss::properties::text::Hyphens;
+                let keyword = match hyphens {
+                    Hyphens::None => Keyword::None,
+                    Hyphens::Manual => Keyword::Manual,
+                    Hyphens::Auto => Keyword::Auto,
+                };
+                Some(Declaration { property: PropertyId::Hyphens, value: PropertyValue::Keyword(keyword), important })
+            }
             Property::Unparsed(unparsed) => {
                 let property_name = unparsed.property_id.name();
                 if let Some(property_id) = PropertyId::from_name(property_name) {
@@ -156,25 +302,114 @@ impl CssParser {
     }
     
     fn display_to_keyword(&self, display: &lightningcss::properties::display::Display) -> Option<Keyword> {
-        // Convert display to string and match
-        let display_str = format!("{:?}", display).to_lowercase();
-        
-        if display_str.contains("none") {
-            Some(Keyword::None)
-        } else if display_str.contains("flex") {
-            Some(Keyword::Flex)
-        } else if display_str.contains("grid") {
-            Some(Keyword::Grid)
-        } else if display_str.contains("inline-block") || display_str.contains("inlineblock") {
-            Some(Keyword::InlineBlock)
-        } else if display_str.contains("inline") {
-            Some(Keyword::Inline)
-        } else if display_str.contains("block") {
-            Some(Keyword::Block)
-        } else if display_str.contains("contents") {
-            Some(Keyword::Contents)
-        } else {
-            None
+        use lightningcss::properties::display::{Display, DisplayInside, DisplayKeyword, DisplayOutside};
+        
+        match display {
+            Display::Keyword(DisplayKeyword::None) => Some(Keyword::None),
+            Display::Keyword(DisplayKeyword::Contents) => Some(Keyword::Contents),
+            Display::Keyword(_) => None,
+            Display::Pair(pair) => {
+                let inline = matches!(pair.outside, DisplayOutside::Inline);
+                Some(match (&pair.inside, inline) {
+                    (DisplayInside::Flex(_) | DisplayInside::Box(_), false) => Keyword::Flex,
+                    (DisplayInside::Flex(_) | DisplayInside::Box(_), true) => Keyword::InlineFlex,
+                    (DisplayInside::Grid, false) => Keyword::Grid,
+                    (DisplayInside::Grid, true) => Keyword::InlineGrid,
+                    (DisplayInside::FlowRoot, false) => Keyword::FlowRoot,
+                    (DisplayInside::FlowRoot, true) => Keyword::InlineBlock,
+                    (_, true) => Keyword::Inline,
+                    (_, false) => Keyword::Block,
+                })
+            }
+        }
+    }
+    
+    fn convert_font_family(&self, families: &[lightningcss::properties::font::FontFamily]) -> PropertyValue {
+        use lightningcss::properties::font::FontFamily;
+        use lightningcss::stylesheet::PrinterOptions;
+        use lightningcss::traits::ToCss;
+        
+        PropertyValue::List(families.iter().map(|family| {
+            let name = match family {
+                FontFamily::Generic(generic) => generic.as_str().to_string(),
+                FontFamily::FamilyName(name) => name
+                    .to_css_string(PrinterOptions::default())
+                    .map(|s| s.trim_matches('"').to_string())
+                    .unwrap_or_default(),
+            };
+            PropertyValue::String(name)
+        }).collect())
+    }
+    
+    fn convert_font_size(&self, size: &lightningcss::properties::font::FontSize) -> Option<PropertyValue> {
+        use lightningcss::properties::font::{AbsoluteFontSize, FontSize, RelativeFontSize};
+        
+        match size {
+            FontSize::Length(lp) => self.convert_length_percentage(lp),
+            // Keyword sizes with a 16px medium
+            FontSize::Absolute(keyword) => Some(PropertyValue::Length(Length::px(match keyword {
+                AbsoluteFontSize::XXSmall => 9.0,
+                AbsoluteFontSize::XSmall => 10.0,
+                AbsoluteFontSize::Small => 13.0,
+                AbsoluteFontSize::Medium => 16.0,
+                AbsoluteFontSize::Large => 18.0,
+                AbsoluteFontSize::XLarge => 24.0,
+                AbsoluteFontSize::XXLarge => 32.0,
+                AbsoluteFontSize::XXXLarge => 48.0,
+            }))),
+            FontSize::Relative(RelativeFontSize::Smaller) => Some(PropertyValue::Length(Length { value: 1.0 / 1.2, unit: LengthUnit::Em })),
+            FontSize::Relative(RelativeFontSize::Larger) => Some(PropertyValue::Length(Length { value: 1.2, unit: LengthUnit::Em })),
+        }
+    }
+    
+    fn convert_font_weight(&self, weight: &lightningcss::properties::font::FontWeight) -> PropertyValue {
+        use lightningcss::properties::font::{AbsoluteFontWeight, FontWeight};
+        
+        match weight {
+            FontWeight::Absolute(AbsoluteFontWeight::Weight(n)) => PropertyValue::Number(*n),
+            FontWeight::Absolute(AbsoluteFontWeight::Normal) => PropertyValue::Number(400.0),
+            FontWeight::Absolute(AbsoluteFontWeight::Bold) => PropertyValue::Number(700.0),
+            FontWeight::Bolder => PropertyValue::Keyword(Keyword::Bolder),
+            FontWeight::Lighter => PropertyValue::Keyword(Keyword::Lighter),
+        }
+    }
+    
+    fn convert_font_style(&self, style: &lightningcss::properties::font::FontStyle) -> PropertyValue {
+        use lightningcss::properties::font::FontStyle;
+        
+        match style {
+            FontStyle::Normal => PropertyValue::Keyword(Keyword::Normal),
+            FontStyle::Italic => PropertyValue::Keyword(Keyword::Italic),
+            // Oblique carries its angle in degrees
+            FontStyle::Oblique(angle) => PropertyValue::List(vec![
+                PropertyValue::Keyword(Keyword::Oblique),
+                PropertyValue::Number(angle.to_degrees()),
+            ]),
+        }
+    }
+    
+    fn convert_font_stretch(&self, stretch: &lightningcss::properties::font::FontStretch) -> PropertyValue {
+        let percentage: lightningcss::values::percentage::Percentage = stretch.into();
+        PropertyValue::Length(Length { value: percentage.0 * 100.0, unit: LengthUnit::Percent })
+    }
+    
+    fn convert_font_variant(&self, caps: &lightningcss::properties::font::FontVariantCaps) -> Option<PropertyValue> {
+        use lightningcss::properties::font::FontVariantCaps;
+        
+        match caps {
+            FontVariantCaps::Normal => Some(PropertyValue::Keyword(Keyword::Normal)),
+            FontVariantCaps::SmallCaps => Some(PropertyValue::Keyword(Keyword::SmallCaps)),
+            _ => None,
+        }
+    }
+    
+    fn convert_line_height(&self, line_height: &lightningcss::properties::font::LineHeight) -> Option<PropertyValue> {
+        use lightningcss::properties::font::LineHeight;
+        
+        match line_height {
+            LineHeight::Normal => Some(PropertyValue::Keyword(Keyword::Normal)),
+            LineHeight::Number(n) => Some(PropertyValue::Number(*n)),
+            LineHeight::Length(lp) => self.convert_length_percentage(lp),
         }
     }
     
@@ -193,6 +428,39 @@ impl CssParser {
         }
     }
     
+    fn convert_color_scheme(&self, scheme: &lightningcss::properties::ui::ColorScheme) -> PropertyValue {
+        use lightningcss::properties::ui::ColorScheme;
+        
+        let keywords: Vec<_> = [
+            (ColorScheme::Light, Keyword::Light),
+            (ColorScheme::Dark, Keyword::Dark),
+            (ColorScheme::Only, Keyword::Only),
+        ]
+            .into_iter()
+            .filter(|(flag, _)| scheme.contains(*flag))
+            .map(|(_, kw)| PropertyValue::Keyword(kw))
+            .collect();
+        
+        if keywords.is_empty() {
+            PropertyValue::Keyword(Keyword::Normal)
+        } else {
+            PropertyValue::List(keywords)
+        }
+    }
+    
+    fn convert_appearance(&self, appearance: &lightningcss::properties::ui::Appearance) -> Option<PropertyValue> {
+        use lightningcss::properties::ui::Appearance;
+        
+        Some(PropertyValue::Keyword(match appearance {
+            Appearance::None => Keyword::None,
+            Appearance::Textfield => Keyword::Textfield,
+            Appearance::MenulistButton => Keyword::MenulistButton,
+            Appearance::NonStandard(_) => return None,
+            // The remaining widget keywords are compat aliases of auto
+            _ => Keyword::Auto,
+        }))
+    }
+    
     fn convert_size(&self, size: &lightningcss::properties::size::Size) -> Option<PropertyValue> {
         use lightningcss::properties::size::Size;
         
@@ -210,14 +478,11 @@ impl CssParser {
         
         match lp {
             LengthPercentage::Dimension(dim) => {
-                // The Dimension type has a value() method or direct access
-                let debug_str = format!("{:?}", dim);
-                // Parse the debug string to get value - hacky but works for now
-                if let Some(value) = self.parse_dimension_value(&debug_str) {
-                    let unit = self.parse_dimension_unit(&debug_str);
-                    Some(PropertyValue::Length(Length { value, unit }))
-                } else {
-                    None
+                let (value, unit) = dim.to_unit_value();
+                match LengthUnit::from_str(unit) {
+                    Some(unit) => Some(PropertyValue::Length(Length { value, unit })),
+                    // Absolute units (cm, pt, ...) become pixels
+                    None => dim.to_px().map(|px| PropertyValue::Length(Length::px(px))),
                 }
             }
             LengthPercentage::Percentage(p) => {
@@ -226,34 +491,6 @@ impl CssParser {
             _ => None,
         }
     }
-    
-    fn parse_dimension_value(&self, s: &str) -> Option<f32> {
-        // Extract numeric value from debug string like "Dimension { value: 10.0, unit: Px }"
-        let num_str: String = s.chars()
-            .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
-            .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
-            .collect();
-        num_str.parse().ok()
-    }
-    
-    fn parse_dimension_unit(&self, s: &str) -> LengthUnit {
-        let s_lower = s.to_lowercase();
-        if s_lower.contains("px") {
-            LengthUnit::Px
-        } else if s_lower.contains("em") {
-            LengthUnit::Em
-        } else if s_lower.contains("rem") {
-            LengthUnit::Rem
-        } else if s_lower.contains("percent") || s_lower.contains("%") {
-            LengthUnit::Percent
-        } else if s_lower.contains("vw") {
-            LengthUnit::Vw
-        } else if s_lower.contains("vh") {
-            LengthUnit::Vh
-        } else {
-            LengthUnit::Px
-        }
-    }
 }
 
 impl Default for CssParser {
@@ -262,6 +499,56 @@ impl Default for CssParser {
     }
 }
 
+/// Byte range of the rule starting at a 0-based `line` and 1-based UTF-16
+/// `column`, through its closing brace
+fn rule_range(css: &str, line: u32, column: u32) -> Option<std::ops::Range<usize>> {
+    let line_start = match line {
+        0 => 0,
+        n => css.match_indices('\n').nth(n as usize - 1)?.0 + 1,
+    };
+    let mut start = None;
+    let mut units = 1;
+    for (i, c) in css[line_start..].char_indices() {
+        if units >= column {
+            start = Some(line_start + i);
+            break;
+        }
+        units += c.len_utf16() as u32;
+    }
+    let start = start?;
+    
+    // Braces, quotes and comment markers are ASCII, so bytes can be scanned
+    let bytes = css.as_bytes();
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = css[i + 2..].find("*/").map_or(bytes.len(), |end| i + end + 3);
+            }
+            b'{' => depth += 1,
+            b'}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(start..i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -280,6 +567,29 @@ mod tests {
         assert_eq!(stylesheet.len(), 2);
     }
     
+    #[test]
+    fn test_rule_source_ranges() {
+        let css = "/* { */ .foo { content: \"}\"; }\n  #bar { color: red; }";
+        let stylesheet = CssParser::new().parse(css).unwrap();
+        let ranges: Vec<&str> = stylesheet.rules.iter()
+            .map(|r| &css[r.source_range.clone().unwrap()])
+            .collect();
+        assert_eq!(ranges, vec![".foo { content: \"}\"; }", "#bar { color: red; }"]);
+    }
+    
+    #[test]
+    fn test_media_rules() {
+        let css = "p { color: red }\n@media (prefers-reduced-data: reduce) { img { display: none } }\n@media print { p { color: black } }";
+        assert_eq!(CssParser::new().parse(css).unwrap().rules.len(), 1);
+        
+        let mut env = MediaEnvironment::new(800.0, 600.0);
+        assert_eq!(CssParser::new().parse_with_media(css, &env).unwrap().rules.len(), 1);
+        env.reduced_data = true;
+        let stylesheet = CssParser::new().parse_with_media(css, &env).unwrap();
+        assert_eq!(stylesheet.rules.len(), 2);
+        assert_eq!(&css[stylesheet.rules[1].source_range.clone().unwrap()], "img { display: none }");
+    }
+    
     #[test]
     fn test_parse_colors() {
         let css = r#"
@@ -292,4 +602,152 @@ mod tests {
         let result = CssParser::new().parse(css);
         assert!(result.is_ok(), "Parse error: {:?}", result.err());
     }
+    
+    #[test]
+    fn test_parse_display_values() {
+        let css = r#"
+            a { display: flow-root; }
+            b { display: inline-flex; }
+            c { display: inline grid; }
+            d { display: inline-block; }
+            e { display: contents; }
+        "#;
+        
+        let stylesheet = CssParser::new().parse(css).unwrap();
+        let keywords: Vec<_> = stylesheet.rules.iter()
+            .filter_map(|r| match r.declarations[0].value {
+                PropertyValue::Keyword(kw) => Some(kw),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(keywords, vec![
+            Keyword::FlowRoot,
+            Keyword::InlineFlex,
+            Keyword::InlineGrid,
+            Keyword::InlineBlock,
+            Keyword::Contents,
+        ]);
+    }
+    
+    #[test]
+    fn test_parse_length_units() {
+        let css = r#"
+            a { width: 50dvh; }
+            b { width: 10svmin; }
+            c { width: 2rem; }
+            d { width: 1in; }
+        "#;
+        
+        let stylesheet = CssParser::new().parse(css).unwrap();
+        let lengths: Vec<_> = stylesheet.rules.iter()
+            .filter_map(|r| match &r.declarations[0].value {
+                PropertyValue::Length(len) => Some((len.value, len.unit)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lengths, vec![
+            (50.0, LengthUnit::Dvh),
+            (10.0, LengthUnit::Svmin),
+            (2.0, LengthUnit::Rem),
+            (96.0, LengthUnit::Px),
+        ]);
+    }
+    
+    #[test]
+    fn test_font_shorthand() {
+        let css = r#"
+            a { font: italic small-caps bold condensed 12px/1.5 "Helvetica Neue", sans-serif; }
+            b { font-weight: 300; font: 2em serif; }
+            c { font: status-bar; }
+        "#;
+        
+        let stylesheet = CssParser::new().parse(css).unwrap();
+        let a = &stylesheet.rules[0].declarations;
+        let properties: Vec<_> = a.iter().map(|d| d.property).collect();
+        assert_eq!(properties, vec![
+            PropertyId::FontStyle,
+            PropertyId::FontVariant,
+            PropertyId::FontWeight,
+            PropertyId::FontStretch,
+            PropertyId::FontSize,
+            PropertyId::LineHeight,
+            PropertyId::FontFamily,
+        ]);
+        assert!(matches!(a[1].value, PropertyValue::Keyword(Keyword::SmallCaps)));
+        assert!(matches!(a[2].value, PropertyValue::Number(w) if w == 700.0));
+        assert!(matches!(a[3].value, PropertyValue::Length(Length { value, unit: LengthUnit::Percent }) if value == 75.0));
+        assert!(matches!(a[5].value, PropertyValue::Number(n) if n == 1.5));
+        let PropertyValue::List(families) = &a[6].value else { panic!("families") };
+        assert!(matches!(&families[..], [PropertyValue::String(f), PropertyValue::String(g)] if f == "Helvetica Neue" && g == "sans-serif"));
+        
+        // The shorthand resets the weight set before it
+        let mut style = crate::ComputedStyle::default();
+        for decl in &stylesheet.rules[1].declarations {
+            style.apply_declaration(decl);
+        }
+        assert_eq!(style.font_weight, 400);
+        assert!((style.line_height - 1.2).abs() < 1e-6);
+        
+        // System fonts expand like any other font value
+        let c = &stylesheet.rules[2].declarations;
+        assert_eq!(c.len(), 7);
+        assert!(matches!(c[4].value, PropertyValue::Length(Length { value, unit: LengthUnit::Px }) if value == 11.0));
+    }
+    
+    #[test]
+    fn test_form_control_properties() {
+        use crate::computed::{AccentColor, Appearance, ColorScheme};
+        
+        let css = r#"
+            a { accent-color: #ff0000; color-scheme: light dark; appearance: none; }
+            b { accent-color: auto; color-scheme: only dark; -webkit-appearance: checkbox; }
+            c { color-scheme: normal; appearance: menulist-button; }
+        "#;
+        
+        let stylesheet = CssParser::new().parse(css).unwrap();
+        let styles: Vec<_> = stylesheet.rules.iter()
+            .map(|rule| {
+                let mut style = crate::ComputedStyle::default();
+                for decl in &rule.declarations {
+                    style.apply_declaration(decl);
+                }
+                style
+            })
+            .collect();
+        
+        assert!(matches!(styles[0].accent_color, AccentColor::Color(c) if c.r == 255 && c.g == 0));
+        assert_eq!(styles[0].color_scheme, ColorScheme { light: true, dark: true, only: false });
+        assert!(styles[0].color_scheme.uses_dark(true) && !styles[0].color_scheme.uses_dark(false));
+        assert_eq!(styles[0].appearance, Appearance::None);
+        
+        assert!(matches!(styles[1].accent_color, AccentColor::Auto));
+        assert!(styles[1].color_scheme.only && styles[1].color_scheme.uses_dark(false));
+        assert_eq!(styles[1].appearance, Appearance::Auto);
+        
+        assert_eq!(styles[2].color_scheme, ColorScheme::NORMAL);
+        assert_eq!(styles[2].appearance, Appearance::MenulistButton);
+        assert!(styles[2].property_mask.is_set(crate::computed::PropertyMask::COLOR_SCHEME));
+    }
+    
+    #[test]
+    fn test_hyphens() {
+        use crate::Hyphens;
+        
+        let stylesheet = CssParser::new().parse("a { hyphens: auto } b { -webkit-hyphens: none } c { hyphens: manual }").unwrap();
+        let styles: Vec<_> = stylesheet.rules.iter()
+            .map(|rule| {
+                let mut style = crate::ComputedStyle::default();
+                for decl in &rule.declarations {
+                    style.apply_declaration(decl);
+                }
+                style
+            })
+            .collect();
+        
+        assert_eq!(styles[0].hyphens, Hyphens::Auto);
+        assert_eq!(styles[1].hyphens, Hyphens::None);
+        assert_eq!(styles[2].hyphens, Hyphens::Manual);
+        assert!(styles[2].property_mask.is_set(crate::computed::PropertyMask::HYPHENS));
+        assert!(!crate::ComputedStyle::default().property_mask.is_set(crate::computed::PropertyMask::HYPHENS));
+    }
 }
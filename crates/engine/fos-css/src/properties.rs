@@ -53,22 +53,32 @@ pub enum PropertyId {
     BackgroundColor,
     Background,
     Opacity,
+    AccentColor,
+    ColorScheme,
+    
+    // Form controls
+    Appearance,
     
     // Text
+    Font,
     FontFamily,
     FontSize,
     FontWeight,
     FontStyle,
+    FontStretch,
+    FontVariant,
     TextAlign,
     TextDecoration,
     LineHeight,
     LetterSpacing,
     WhiteSpace,
+    Hyphens,
     
     // Visual
     Overflow,
     OverflowX,
     OverflowY,
+    OverflowAnchor,
     Visibility,
     ZIndex,
     
@@ -134,20 +144,29 @@ impl PropertyId {
             "background-color" => Self::BackgroundColor,
             "background" => Self::Background,
             "opacity" => Self::Opacity,
+            "accent-color" => Self::AccentColor,
+            "color-scheme" => Self::ColorScheme,
+            
+            "appearance" => Self::Appearance,
             
+            "font" => Self::Font,
             "font-family" => Self::FontFamily,
             "font-size" => Self::FontSize,
             "font-weight" => Self::FontWeight,
             "font-style" => Self::FontStyle,
+            "font-stretch" => Self::FontStretch,
+            "font-variant" => Self::FontVariant,
             "text-align" => Self::TextAlign,
             "text-decoration" => Self::TextDecoration,
             "line-height" => Self::LineHeight,
             "letter-spacing" => Self::LetterSpacing,
             "white-space" => Self::WhiteSpace,
+            "hyphens" => Self::Hyphens,
             
             "overflow" => Self::Overflow,
             "overflow-x" => Self::OverflowX,
             "overflow-y" => Self::OverflowY,
+            "overflow-anchor" => Self::OverflowAnchor,
             "visibility" => Self::Visibility,
             "z-index" => Self::ZIndex,
             
@@ -164,6 +183,17 @@ impl PropertyId {
             _ => return None,
         })
     }
+    
+    /// CSS name of the property (`font-size` for `FontSize`)
+    pub fn name(&self) -> String {
+        kebab_case(&format!("{:?}", self))
+    }
+    
+    /// Whether `:visited` rules may set the property. Only colors, which
+    /// pages cannot read back, so styling cannot reveal browsing history.
+    pub fn applies_to_visited(&self) -> bool {
+        matches!(self, Self::Color | Self::BackgroundColor | Self::BorderColor)
+    }
 }
 
 /// Property value - parsed and typed
@@ -187,6 +217,44 @@ pub enum PropertyValue {
     Raw(String),
 }
 
+impl PropertyValue {
+    /// Serialize the value back to CSS text
+    pub fn to_css(&self) -> String {
+        match self {
+            Self::Keyword(k) => k.name(),
+            Self::Length(l) => {
+                let unit = match l.unit {
+                    LengthUnit::Percent => "%".to_string(),
+                    unit => format!("{:?}", unit).to_ascii_lowercase(),
+                };
+                format!("{}{}", l.value, unit)
+            }
+            Self::Color(c) if c.a == 255 => format!("rgb({}, {}, {})", c.r, c.g, c.b),
+            Self::Color(c) => format!("rgba({}, {}, {}, {})", c.r, c.g, c.b, c.a as f32 / 255.0),
+            Self::Number(n) => n.to_string(),
+            Self::Integer(i) => i.to_string(),
+            Self::String(s) | Self::Raw(s) => s.clone(),
+            Self::List(values) => values.iter().map(Self::to_css).collect::<Vec<_>>().join(" "),
+        }
+    }
+}
+
+/// `FlexStart` -> `flex-start`
+fn kebab_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('-');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
 /// CSS keyword values
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Keyword {
@@ -206,8 +274,11 @@ pub enum Keyword {
     Block,
     Inline,
     InlineBlock,
+    FlowRoot,
     Flex,
+    InlineFlex,
     Grid,
+    InlineGrid,
     Contents,
     
     // Position
@@ -257,6 +328,19 @@ pub enum Keyword {
     Lighter,
     Italic,
     Oblique,
+    SmallCaps,
+    
+    // Color scheme
+    Light,
+    Dark,
+    Only,
+    
+    // Appearance
+    Textfield,
+    MenulistButton,
+    
+    // Hyphens
+    Manual,
 }
 
 impl Keyword {
@@ -273,8 +357,11 @@ impl Keyword {
             "block" => Self::Block,
             "inline" => Self::Inline,
             "inline-block" => Self::InlineBlock,
+            "flow-root" => Self::FlowRoot,
             "flex" => Self::Flex,
+            "inline-flex" => Self::InlineFlex,
             "grid" => Self::Grid,
+            "inline-grid" => Self::InlineGrid,
             "contents" => Self::Contents,
             "static" => Self::Static,
             "relative" => Self::Relative,
@@ -312,9 +399,21 @@ impl Keyword {
             "lighter" => Self::Lighter,
             "italic" => Self::Italic,
             "oblique" => Self::Oblique,
+            "small-caps" => Self::SmallCaps,
+            "light" => Self::Light,
+            "dark" => Self::Dark,
+            "only" => Self::Only,
+            "textfield" => Self::Textfield,
+            "menulist-button" => Self::MenulistButton,
+            "manual" => Self::Manual,
             _ => return None,
         })
     }
+    
+    /// CSS spelling of the keyword
+    pub fn name(&self) -> String {
+        kebab_case(&format!("{:?}", self))
+    }
 }
 
 /// CSS length value
@@ -353,10 +452,73 @@ pub enum LengthUnit {
     Vh,
     Vmin,
     Vmax,
+    /// Small viewport units (retractable UI shown)
+    Svw,
+    Svh,
+    Svmin,
+    Svmax,
+    /// Large viewport units (retractable UI hidden)
+    Lvw,
+    Lvh,
+    Lvmin,
+    Lvmax,
+    /// Dynamic viewport units (current UI state)
+    Dvw,
+    Dvh,
+    Dvmin,
+    Dvmax,
     Ch,
     Ex,
 }
 
+impl LengthUnit {
+    /// Parse a CSS unit name (case-insensitive)
+    pub fn from_str(s: &str) -> Option<Self> {
+        Some(match s.to_ascii_lowercase().as_str() {
+            "px" => Self::Px,
+            "em" => Self::Em,
+            "rem" => Self::Rem,
+            "%" => Self::Percent,
+            "vw" => Self::Vw,
+            "vh" => Self::Vh,
+            "vmin" => Self::Vmin,
+            "vmax" => Self::Vmax,
+            "svw" => Self::Svw,
+            "svh" => Self::Svh,
+            "svmin" => Self::Svmin,
+            "svmax" => Self::Svmax,
+            "lvw" => Self::Lvw,
+            "lvh" => Self::Lvh,
+            "lvmin" => Self::Lvmin,
+            "lvmax" => Self::Lvmax,
+            "dvw" => Self::Dvw,
+            "dvh" => Self::Dvh,
+            "dvmin" => Self::Dvmin,
+            "dvmax" => Self::Dvmax,
+            "ch" => Self::Ch,
+            "ex" => Self::Ex,
+            _ => return None,
+        })
+    }
+    
+    /// Whether the unit is relative to the viewport size
+    pub fn is_viewport_relative(&self) -> bool {
+        use LengthUnit::*;
+        matches!(
+            self,
+            Vw | Vh | Vmin | Vmax
+                | Svw | Svh | Svmin | Svmax
+                | Lvw | Lvh | Lvmin | Lvmax
+                | Dvw | Dvh | Dvmin | Dvmax
+        )
+    }
+    
+    /// Whether the unit follows retractable UI showing and hiding
+    pub fn is_dynamic_viewport(&self) -> bool {
+        matches!(self, Self::Dvw | Self::Dvh | Self::Dvmin | Self::Dvmax)
+    }
+}
+
 /// CSS color
 #[derive(Debug, Clone, Copy)]
 pub struct Color {
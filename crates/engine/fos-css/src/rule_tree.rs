@@ -39,6 +39,10 @@ pub enum RuleSource {
 }
 
 /// Cascade level for ordering
+///
+/// Follows the origin order of CSS Cascade: animations override normal
+/// declarations but lose to `!important` ones, and transitions override
+/// everything.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CascadeLevel(pub u8);
 
@@ -46,10 +50,10 @@ impl CascadeLevel {
     pub const UA_NORMAL: CascadeLevel = CascadeLevel(0);
     pub const USER_NORMAL: CascadeLevel = CascadeLevel(1);
     pub const AUTHOR_NORMAL: CascadeLevel = CascadeLevel(2);
-    pub const AUTHOR_IMPORTANT: CascadeLevel = CascadeLevel(3);
-    pub const USER_IMPORTANT: CascadeLevel = CascadeLevel(4);
-    pub const UA_IMPORTANT: CascadeLevel = CascadeLevel(5);
-    pub const ANIMATION: CascadeLevel = CascadeLevel(6);
+    pub const ANIMATION: CascadeLevel = CascadeLevel(3);
+    pub const AUTHOR_IMPORTANT: CascadeLevel = CascadeLevel(4);
+    pub const USER_IMPORTANT: CascadeLevel = CascadeLevel(5);
+    pub const UA_IMPORTANT: CascadeLevel = CascadeLevel(6);
     pub const TRANSITION: CascadeLevel = CascadeLevel(7);
 }
 
@@ -4,6 +4,7 @@
 //! https://www.w3.org/TR/css-transitions-1/
 
 use std::collections::HashMap;
+use crate::interpolation::{AnimatableValue, AnimationType, interpolate_property};
 
 // Local Fixed16 to avoid circular dependency with fos-engine
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
@@ -168,6 +169,8 @@ pub struct Transition {
     pub delay_ms: Fixed16,
     /// Timing function
     pub timing: TimingFunction,
+    /// `allow-discrete`: also transition values that can only flip
+    pub allow_discrete: bool,
 }
 
 impl Default for Transition {
@@ -177,6 +180,7 @@ impl Default for Transition {
             duration_ms: Fixed16::ZERO,
             delay_ms: Fixed16::ZERO,
             timing: TimingFunction::Ease,
+            allow_discrete: false,
         }
     }
 }
@@ -189,6 +193,7 @@ impl Transition {
             duration_ms: Fixed16::from_f32(duration_ms),
             delay_ms: Fixed16::ZERO,
             timing: TimingFunction::Ease,
+            allow_discrete: false,
         }
     }
     
@@ -204,6 +209,12 @@ impl Transition {
         self
     }
     
+    /// Transition discrete values too, flipping halfway
+    pub fn with_allow_discrete(mut self) -> Self {
+        self.allow_discrete = true;
+        self
+    }
+    
     /// Parse from CSS value string
     pub fn parse(value: &str) -> Option<Transition> {
         let parts: Vec<&str> = value.split_whitespace().collect();
@@ -231,7 +242,9 @@ impl Transition {
         // Parse timing function or delay
         while idx < parts.len() {
             let part = parts[idx];
-            if let Some(timing) = parse_timing_function(part) {
+            if part == "allow-discrete" {
+                transition.allow_discrete = true;
+            } else if let Some(timing) = parse_timing_function(part) {
                 transition.timing = timing;
             } else if let Some(delay) = parse_time(part) {
                 transition.delay_ms = Fixed16::from_f32(delay);
@@ -253,7 +266,7 @@ fn parse_time(s: &str) -> Option<f32> {
     }
 }
 
-fn parse_timing_function(s: &str) -> Option<TimingFunction> {
+pub(crate) fn parse_timing_function(s: &str) -> Option<TimingFunction> {
     match s {
         "linear" => Some(TimingFunction::Linear),
         "ease" => Some(TimingFunction::Ease),
@@ -302,6 +315,9 @@ pub struct ActiveTransition {
     pub delay_ms: Fixed16,
     /// Timing function
     pub timing: TimingFunction,
+    /// CSS values of a value transition; `start_value` and `end_value` are
+    /// then 0 and 1
+    pub values: Option<(AnimatableValue, AnimatableValue)>,
 }
 
 impl ActiveTransition {
@@ -315,6 +331,7 @@ impl ActiveTransition {
             duration_ms: def.duration_ms,
             delay_ms: def.delay_ms,
             timing: def.timing,
+            values: None,
         }
     }
     
@@ -337,16 +354,30 @@ impl ActiveTransition {
             return self.end_value;
         }
         
+        let t = self.eased_progress().to_f32();
+        self.start_value + (self.end_value - self.start_value) * t
+    }
+    
+    /// Current CSS value of a value transition
+    pub fn current_css_value(&self) -> Option<String> {
+        let (from, to) = self.values.as_ref()?;
+        let t = if !self.has_started() {
+            0.0
+        } else if self.is_complete() {
+            1.0
+        } else {
+            self.eased_progress().to_f32() as f64
+        };
+        Some(interpolate_property(&self.property, from, to, t).to_string())
+    }
+    
+    fn eased_progress(&self) -> Fixed16 {
         let progress = if self.duration_ms.0 == 0 {
             Fixed16::ONE
         } else {
             self.elapsed_ms / self.duration_ms
         };
-        
-        let eased = self.timing.evaluate(progress);
-        let t = eased.to_f32();
-        
-        self.start_value + (self.end_value - self.start_value) * t
+        self.timing.evaluate(progress)
     }
     
     /// Advance the transition by delta milliseconds
@@ -393,24 +424,112 @@ impl TransitionEngine {
         end_value: f32,
     ) {
         // Find matching transition definition
-        let def = self.definitions.get(&element_id)
-            .and_then(|defs| {
-                defs.iter().find(|d| d.property == property || d.property == "all" || d.property.is_empty())
-            });
-        
-        if let Some(def) = def {
+        if let Some(def) = self.definition(element_id, property) {
             let key = (element_id, property.to_string());
             let active = ActiveTransition::new(property, start_value, end_value, def);
             self.transitions.insert(key, active);
         }
     }
     
+    fn definition(&self, element_id: u64, property: &str) -> Option<&Transition> {
+        self.definitions.get(&element_id)
+            .and_then(|defs| {
+                defs.iter().find(|d| d.property == property || d.property == "all" || d.property.is_empty())
+            })
+    }
+    
+    /// Start a transition between two CSS values of any animatable
+    /// property. Values that cannot be interpolated only transition when
+    /// the definition allows discrete ones; otherwise, and for properties
+    /// that are not animatable, the value changes at once and false is
+    /// returned.
+    pub fn start_value_transition(
+        &mut self,
+        element_id: u64,
+        property: &str,
+        from: &str,
+        to: &str,
+    ) -> bool {
+        let key = (element_id, property.to_string());
+        self.transitions.remove(&key);
+        let kind = AnimationType::of(property);
+        if kind == AnimationType::NotAnimatable {
+            return false;
+        }
+        let Some(def) = self.definition(element_id, property) else {
+            return false;
+        };
+        
+        let from = AnimatableValue::parse(property, from);
+        let to = AnimatableValue::parse(property, to);
+        // `visibility` has always transitioned without `allow-discrete`
+        let interpolable = from.is_interpolable_with(&to) || kind == AnimationType::Visibility;
+        if from == to || !(interpolable || def.allow_discrete) {
+            return false;
+        }
+        
+        let mut active = ActiveTransition::new(property, 0.0, 1.0, def);
+        active.values = Some((from, to));
+        self.transitions.insert(key, active);
+        true
+    }
+    
+    /// Start a `font-variation-settings` transition. Each axis runs as its
+    /// own transition when both lists set the same axes; otherwise the
+    /// value changes discretely and false is returned.
+    pub fn start_variation_transition(
+        &mut self,
+        element_id: u64,
+        from: &[([u8; 4], f32)],
+        to: &[([u8; 4], f32)],
+    ) -> bool {
+        let from = sorted_axes(from);
+        let to = sorted_axes(to);
+        if from.len() != to.len() || from.iter().zip(&to).any(|(a, b)| a.0 != b.0) {
+            return false;
+        }
+        let Some(def) = self.definition(element_id, VARIATION_PROPERTY).cloned() else {
+            return false;
+        };
+        
+        self.transitions.retain(|(id, property), _| *id != element_id || !property.starts_with(VARIATION_PROPERTY));
+        for ((tag, start), (_, end)) in from.into_iter().zip(to) {
+            let property = variation_axis_property(&tag);
+            let active = ActiveTransition::new(&property, start, end, &def);
+            self.transitions.insert((element_id, property), active);
+        }
+        true
+    }
+    
+    /// Current `font-variation-settings` of an element while it
+    /// transitions, sorted by axis tag
+    pub fn variation_settings(&self, element_id: u64) -> Option<Vec<([u8; 4], f32)>> {
+        let mut axes: Vec<([u8; 4], f32)> = self.transitions.iter()
+            .filter(|((id, _), _)| *id == element_id)
+            .filter_map(|((_, property), t)| {
+                let tag = property.strip_prefix(VARIATION_PROPERTY)?.strip_prefix(':')?;
+                Some((tag.as_bytes().try_into().ok()?, t.current_value()))
+            })
+            .collect();
+        if axes.is_empty() {
+            return None;
+        }
+        axes.sort_by_key(|(tag, _)| *tag);
+        Some(axes)
+    }
+    
     /// Get current value for a transitioning property
     pub fn get_value(&self, element_id: u64, property: &str) -> Option<f32> {
         let key = (element_id, property.to_string());
         self.transitions.get(&key).map(|t| t.current_value())
     }
     
+    /// Current CSS value of a property in a value transition
+    pub fn get_css_value(&self, element_id: u64, property: &str) -> Option<String> {
+        let key = (element_id, property.to_string());
+        self.transitions.get(&key).and_then(|t| t.current_css_value())
+    }
+    
     /// Advance all transitions by delta time
     pub fn tick(&mut self, delta_ms: f32) {
         let delta = Fixed16::from_f32(delta_ms);
@@ -436,6 +555,22 @@ impl TransitionEngine {
     }
 }
 
+const VARIATION_PROPERTY: &str = "font-variation-settings";
+
+/// Transition key of one axis, e.g. `font-variation-settings:wght`
+fn variation_axis_property(tag: &[u8; 4]) -> String {
+    format!("{}:{}", VARIATION_PROPERTY, String::from_utf8_lossy(tag))
+}
+
+/// Axes sorted by tag, the last value of a repeated tag winning
+fn sorted_axes(axes: &[([u8; 4], f32)]) -> Vec<([u8; 4], f32)> {
+    let mut map = std::collections::BTreeMap::new();
+    for &(tag, value) in axes {
+        map.insert(tag, value);
+    }
+    map.into_iter().collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -529,4 +664,51 @@ mod tests {
         engine.tick(200.0);
         assert!(!engine.has_active_transitions()); // completed and removed
     }
+    
+    #[test]
+    fn test_variation_transition() {
+        let mut engine = TransitionEngine::new();
+        engine.set_definitions(1, vec![
+            Transition::new("font-variation-settings", 200.0)
+                .with_timing(TimingFunction::Linear),
+        ]);
+        
+        let from = [(*b"wght", 400.0), (*b"wdth", 100.0)];
+        let to = [(*b"wdth", 50.0), (*b"wght", 800.0)];
+        assert!(engine.start_variation_transition(1, &from, &to));
+        engine.tick(100.0);
+        let axes = engine.variation_settings(1).unwrap();
+        assert_eq!(axes[0].0, *b"wdth");
+        assert!((axes[0].1 - 75.0).abs() < 0.5 && (axes[1].1 - 600.0).abs() < 2.0, "{:?}", axes);
+        
+        // Different axes switch discretely
+        assert!(!engine.start_variation_transition(1, &from, &[(*b"wght", 900.0)]));
+        engine.tick(200.0);
+        assert!(engine.variation_settings(1).is_none());
+    }
+    
+    #[test]
+    fn test_value_transitions() {
+        let mut engine = TransitionEngine::new();
+        engine.set_definitions(1, vec![
+            Transition::new("all", 100.0).with_timing(TimingFunction::Linear),
+        ]);
+        engine.set_definitions(2, vec![
+            Transition::parse("display 100ms linear allow-discrete").unwrap(),
+            Transition::new("position", 100.0).with_timing(TimingFunction::Linear),
+        ]);
+        
+        assert!(engine.start_value_transition(1, "background-color", "#ff0000", "rgba(0, 0, 255, 1)"));
+        assert!(engine.start_value_transition(1, "transform", "rotate(0deg)", "rotate(90deg) scale(2)"));
+        assert!(!engine.start_value_transition(1, "transition-duration", "1s", "2s"));
+        engine.tick(50.0);
+        assert_eq!(engine.get_css_value(1, "background-color").unwrap(), "rgb(128, 0, 128)");
+        assert!(engine.get_css_value(1, "transform").unwrap().starts_with("matrix("));
+        
+        // Discrete values only transition with allow-discrete
+        assert!(!engine.start_value_transition(2, "position", "static", "absolute"));
+        assert!(engine.start_value_transition(2, "display", "none", "block"));
+        engine.tick(10.0);
+        assert_eq!(engine.get_css_value(2, "display").unwrap(), "block");
+    }
 }
@@ -0,0 +1,179 @@
+//! Viewport Units
+//!
+//! Resolution of vw/vh/vmin/vmax and their small (sv*), large (lv*) and
+//! dynamic (dv*) variants. The large viewport is the layout viewport with
+//! retractable browser UI hidden, the small one with it shown, and the
+//! dynamic one follows whichever state the UI is currently in. Pinch zoom
+//! only scales the visual viewport, so it never changes unit values.
+
+use crate::properties::LengthUnit;
+
+/// Layout viewport state used to resolve viewport-relative units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// Layout viewport width in CSS pixels
+    pub width: f32,
+    /// Layout viewport height with retractable UI hidden
+    pub height: f32,
+    /// Height taken by retractable UI (toolbars) when shown
+    pub chrome_inset: f32,
+    /// Whether the retractable UI is currently shown
+    pub chrome_visible: bool,
+    /// Pinch zoom scale of the visual viewport
+    pub pinch_zoom: f32,
+}
+
+/// What a viewport update invalidated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewportChange {
+    /// No unit resolves differently
+    Unchanged,
+    /// Only dv* units resolve differently
+    DynamicUnits,
+    /// All viewport units resolve differently
+    Resized,
+}
+
+impl ViewportChange {
+    /// Whether styles using viewport units need restyle and relayout
+    pub fn needs_relayout(&self) -> bool {
+        !matches!(self, Self::Unchanged)
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::new(0.0, 0.0)
+    }
+}
+
+impl Viewport {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            chrome_inset: 0.0,
+            chrome_visible: false,
+            pinch_zoom: 1.0,
+        }
+    }
+
+    /// With retractable UI of the given height, initially shown
+    pub fn with_chrome(mut self, inset: f32) -> Self {
+        self.chrome_inset = inset.max(0.0);
+        self.chrome_visible = true;
+        self
+    }
+
+    /// Viewport size with retractable UI shown
+    pub fn small(&self) -> (f32, f32) {
+        (self.width, (self.height - self.chrome_inset).max(0.0))
+    }
+
+    /// Viewport size with retractable UI hidden
+    pub fn large(&self) -> (f32, f32) {
+        (self.width, self.height)
+    }
+
+    /// Viewport size for the current UI state
+    pub fn dynamic(&self) -> (f32, f32) {
+        if self.chrome_visible {
+            self.small()
+        } else {
+            self.large()
+        }
+    }
+
+    /// Visible area in CSS pixels after pinch zoom
+    pub fn visual(&self) -> (f32, f32) {
+        let (width, height) = self.dynamic();
+        let zoom = if self.pinch_zoom > 0.0 { self.pinch_zoom } else { 1.0 };
+        (width / zoom, height / zoom)
+    }
+
+    /// Resolve a viewport-relative length to pixels; `None` for other units
+    pub fn resolve(&self, value: f32, unit: LengthUnit) -> Option<f32> {
+        use LengthUnit::*;
+
+        // Plain v* units use the large viewport, like other engines
+        let (width, height) = match unit {
+            Vw | Vh | Vmin | Vmax | Lvw | Lvh | Lvmin | Lvmax => self.large(),
+            Svw | Svh | Svmin | Svmax => self.small(),
+            Dvw | Dvh | Dvmin | Dvmax => self.dynamic(),
+            _ => return None,
+        };
+        let basis = match unit {
+            Vw | Svw | Lvw | Dvw => width,
+            Vh | Svh | Lvh | Dvh => height,
+            Vmin | Svmin | Lvmin | Dvmin => width.min(height),
+            _ => width.max(height),
+        };
+        Some(value * basis / 100.0)
+    }
+
+    /// Resize the layout viewport
+    pub fn resize(&mut self, width: f32, height: f32) -> ViewportChange {
+        if (width, height) == (self.width, self.height) {
+            return ViewportChange::Unchanged;
+        }
+        self.width = width;
+        self.height = height;
+        ViewportChange::Resized
+    }
+
+    /// Show or hide the retractable UI
+    pub fn set_chrome_visible(&mut self, visible: bool) -> ViewportChange {
+        if visible == self.chrome_visible {
+            return ViewportChange::Unchanged;
+        }
+        self.chrome_visible = visible;
+        if self.chrome_inset > 0.0 {
+            ViewportChange::DynamicUnits
+        } else {
+            ViewportChange::Unchanged
+        }
+    }
+
+    /// Set the pinch zoom scale; viewport units are unaffected
+    pub fn set_pinch_zoom(&mut self, scale: f32) -> ViewportChange {
+        self.pinch_zoom = scale;
+        ViewportChange::Unchanged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_variants() {
+        let viewport = Viewport::new(400.0, 800.0).with_chrome(100.0);
+        assert_eq!(viewport.resolve(50.0, LengthUnit::Vw), Some(200.0));
+        assert_eq!(viewport.resolve(100.0, LengthUnit::Vh), Some(800.0));
+        assert_eq!(viewport.resolve(100.0, LengthUnit::Lvh), Some(800.0));
+        assert_eq!(viewport.resolve(100.0, LengthUnit::Svh), Some(700.0));
+        assert_eq!(viewport.resolve(100.0, LengthUnit::Dvh), Some(700.0));
+        assert_eq!(viewport.resolve(10.0, LengthUnit::Vmin), Some(40.0));
+        assert_eq!(viewport.resolve(10.0, LengthUnit::Svmax), Some(70.0));
+        assert_eq!(viewport.resolve(10.0, LengthUnit::Px), None);
+    }
+
+    #[test]
+    fn test_updates() {
+        let mut viewport = Viewport::new(400.0, 800.0).with_chrome(100.0);
+
+        // Hiding the toolbar only moves dynamic units
+        assert_eq!(viewport.set_chrome_visible(false), ViewportChange::DynamicUnits);
+        assert_eq!(viewport.resolve(100.0, LengthUnit::Dvh), Some(800.0));
+        assert_eq!(viewport.resolve(100.0, LengthUnit::Svh), Some(700.0));
+        assert_eq!(viewport.set_chrome_visible(false), ViewportChange::Unchanged);
+
+        // Pinch zoom shrinks the visual viewport but not the units
+        assert!(!viewport.set_pinch_zoom(2.0).needs_relayout());
+        assert_eq!(viewport.visual(), (200.0, 400.0));
+        assert_eq!(viewport.resolve(100.0, LengthUnit::Vw), Some(400.0));
+
+        assert_eq!(viewport.resize(500.0, 800.0), ViewportChange::Resized);
+        assert_eq!(viewport.resolve(100.0, LengthUnit::Vw), Some(500.0));
+    }
+}
@@ -3,8 +3,13 @@
 //! Implementation of the Web Animations API for JavaScript access to CSS animations.
 //! Uses Fixed16 for deterministic timing calculations.
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::sync::Arc;
+use crate::interpolation::{AnimatableValue, interpolate_property, transform_matrix};
+use crate::parallel_css_parser::KeyframesRule;
+use crate::rule_tree::CascadeLevel;
+use crate::transitions::{self, Fixed16 as EasingFixed16};
 
 // ============================================================================
 // Fixed-Point Timing for Deterministic Animations
@@ -179,6 +184,10 @@ pub struct Animation {
     pub finished: bool,
     /// Pending flag
     pub pending: bool,
+    /// What created the animation
+    pub class: AnimationClass,
+    /// Position in the document's global animation list
+    pub sequence: u64,
 }
 
 impl Default for Animation {
@@ -194,6 +203,42 @@ impl Default for Animation {
             ready: false,
             finished: false,
             pending: false,
+            class: AnimationClass::Script,
+            sequence: 0,
+        }
+    }
+}
+
+/// What created an animation, which decides its composite order and the
+/// cascade origin its values apply in
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum AnimationClass {
+    /// CSS transition of `property`, started in transition generation
+    /// `generation`
+    CssTransition { generation: u64, property: String },
+    /// CSS animation at position `index` of `animation-name`, whose
+    /// `animation-timing-function` eases keyframes without their own
+    CssAnimation { name: String, index: usize, easing: String },
+    /// Created by script
+    #[default]
+    Script,
+}
+
+impl AnimationClass {
+    /// Cascade origin of the animated values: transitions override
+    /// `!important` declarations, animations only normal ones
+    pub fn cascade_level(&self) -> CascadeLevel {
+        match self {
+            Self::CssTransition { .. } => CascadeLevel::TRANSITION,
+            _ => CascadeLevel::ANIMATION,
+        }
+    }
+    
+    fn rank(&self) -> u8 {
+        match self {
+            Self::CssTransition { .. } => 0,
+            Self::CssAnimation { .. } => 1,
+            Self::Script => 2,
         }
     }
 }
@@ -304,6 +349,32 @@ impl Animation {
         }
         None
     }
+    
+    /// Composite order: transitions by generation then property name, CSS
+    /// animations by `animation-name` position, then script animations by
+    /// creation, each later one compositing on top of the earlier ones
+    pub fn composite_cmp(&self, other: &Animation) -> Ordering {
+        let by_class = match (&self.class, &other.class) {
+            (
+                AnimationClass::CssTransition { generation: a, property: pa },
+                AnimationClass::CssTransition { generation: b, property: pb },
+            ) => a.cmp(b).then_with(|| pa.cmp(pb)),
+            (AnimationClass::CssAnimation { index: a, .. }, AnimationClass::CssAnimation { index: b, .. }) => a.cmp(b),
+            (a, b) => a.rank().cmp(&b.rank()),
+        };
+        by_class.then(self.sequence.cmp(&other.sequence))
+    }
+    
+    /// Value of `property` at the current time, or `None` when idle or
+    /// outside the active interval without a fill
+    pub fn sample(&self, property: &str) -> Option<AnimatableValue> {
+        if self.play_state == PlayState::Idle {
+            return None;
+        }
+        let effect = self.effect.as_ref()?;
+        let progress = effect.computed_timing.iteration_progress(self.current_time?)?;
+        effect.sample(property, progress)
+    }
 }
 
 /// Animation effect (KeyframeEffect)
@@ -317,6 +388,40 @@ pub struct AnimationEffect {
     pub computed_timing: ComputedTiming,
 }
 
+impl AnimationEffect {
+    /// Value of `property` at an eased iteration progress
+    ///
+    /// Without keyframes at offsets 0 or 1 the nearest keyframe value is
+    /// held, standing in for the underlying value.
+    pub fn sample(&self, property: &str, progress: f64) -> Option<AnimatableValue> {
+        let frames: Vec<&Keyframe> = self.keyframes.iter()
+            .filter(|kf| kf.properties.contains_key(property))
+            .collect();
+        let value = |kf: &Keyframe| AnimatableValue::parse(property, &kf.properties[property]);
+        
+        let offset = |kf: &Keyframe| kf.offset.unwrap_or(0.0);
+        let Some(start) = frames.iter().rposition(|kf| offset(kf) <= progress) else {
+            return frames.first().map(|kf| value(kf));
+        };
+        let Some(end) = frames.get(start + 1) else {
+            return Some(value(frames[start]));
+        };
+        
+        let from = frames[start];
+        let span = offset(end) - offset(from);
+        let local = if span > 0.0 { (progress - offset(from)) / span } else { 1.0 };
+        Some(interpolate_property(property, &value(from), &value(end), ease(&from.easing, local)))
+    }
+}
+
+/// Apply a CSS easing function, falling back to linear
+fn ease(easing: &str, t: f64) -> f64 {
+    match transitions::parse_timing_function(easing.trim()) {
+        Some(function) => function.evaluate(EasingFixed16::from_f32(t as f32)).to_f32() as f64,
+        None => t,
+    }
+}
+
 /// Single keyframe
 #[derive(Debug, Clone)]
 pub struct Keyframe {
@@ -403,6 +508,52 @@ impl ComputedTiming {
     pub fn calculate_end_time(&self) -> f64 {
         (self.delay + self.active_duration() + self.end_delay).max(0.0)
     }
+    
+    /// Directed and eased progress through the current iteration at
+    /// `local_time`, or `None` outside the active interval without a fill
+    pub fn iteration_progress(&self, local_time: f64) -> Option<f64> {
+        let active_duration = self.active_duration();
+        let before = local_time < self.delay;
+        let after = !before && local_time >= self.delay + active_duration;
+        let active_time = if before {
+            if !matches!(self.fill, FillMode::Backwards | FillMode::Both) {
+                return None;
+            }
+            0.0
+        } else if after {
+            if !matches!(self.fill, FillMode::Forwards | FillMode::Both) {
+                return None;
+            }
+            active_duration
+        } else {
+            local_time - self.delay
+        };
+        
+        let overall = if self.duration > 0.0 {
+            active_time / self.duration
+        } else if after {
+            self.iterations
+        } else {
+            0.0
+        } + self.iteration_start;
+        
+        let mut progress = overall.fract();
+        let mut iteration = overall.floor();
+        if progress == 0.0 && overall != 0.0 && (after || active_time == active_duration) {
+            progress = 1.0;
+            iteration -= 1.0;
+        }
+        
+        let odd = iteration % 2.0 == 1.0;
+        let forwards = match self.direction {
+            PlaybackDirection::Normal => true,
+            PlaybackDirection::Reverse => false,
+            PlaybackDirection::Alternate => !odd,
+            PlaybackDirection::AlternateReverse => odd,
+        };
+        let directed = if forwards { progress } else { 1.0 - progress };
+        Some(ease(&self.easing, directed))
+    }
 }
 
 /// Fill mode
@@ -433,6 +584,10 @@ pub struct DocumentAnimations {
     next_id: u64,
     /// Precomputed keyframe cache
     precomputed: HashMap<String, PrecomputedAnimation>,
+    /// `@keyframes` rules by name, the last one defined winning
+    keyframes: HashMap<String, KeyframesRule>,
+    /// Generation of the next transitions to start
+    transition_generation: u64,
 }
 
 impl DocumentAnimations {
@@ -442,19 +597,125 @@ impl DocumentAnimations {
     
     /// Create a new animation
     pub fn create(&mut self, effect: AnimationEffect) -> String {
+        self.insert(effect, AnimationClass::Script)
+    }
+    
+    fn insert(&mut self, effect: AnimationEffect, class: AnimationClass) -> String {
         let id = format!("animation_{}", self.next_id);
-        self.next_id += 1;
         
         // Check if this animation can be precomputed
         if let Some(precomputed) = KeyframePrecompute::precompute(&effect) {
             self.precomputed.insert(id.clone(), precomputed);
         }
         
-        let animation = Animation::new(&id, effect);
+        let mut animation = Animation::new(&id, effect);
+        animation.class = class;
+        animation.sequence = self.next_id;
+        self.next_id += 1;
         self.animations.insert(id.clone(), animation);
         id
     }
     
+    /// Register an `@keyframes` rule, replacing an earlier one of the same
+    /// name and updating the CSS animations that use it
+    pub fn register_keyframes(&mut self, rule: &KeyframesRule) {
+        if rule.name.is_empty() || rule.name.eq_ignore_ascii_case("none") {
+            return;
+        }
+        self.keyframes.insert(rule.name.clone(), rule.clone());
+        
+        for (id, animation) in &mut self.animations {
+            let (AnimationClass::CssAnimation { name, easing, .. }, Some(effect)) = (&animation.class, &mut animation.effect) else {
+                continue;
+            };
+            if *name != rule.name {
+                continue;
+            }
+            effect.keyframes = resolve_keyframes(rule, easing);
+            match KeyframePrecompute::precompute(effect) {
+                Some(precomputed) => self.precomputed.insert(id.clone(), precomputed),
+                None => self.precomputed.remove(id),
+            };
+        }
+    }
+    
+    /// Keyframes of a registered `@keyframes` rule, eased by `easing` where
+    /// a keyframe sets no `animation-timing-function`
+    pub fn resolve_keyframes(&self, name: &str, easing: &str) -> Option<Vec<Keyframe>> {
+        self.keyframes.get(name).map(|rule| resolve_keyframes(rule, easing))
+    }
+    
+    /// Start the CSS animation at position `index` of the `animation-name`
+    /// list of `target`; `None` when no `@keyframes` rule has that name
+    pub fn start_css_animation(&mut self, target: &str, name: &str, index: usize, mut timing: ComputedTiming) -> Option<String> {
+        let easing = std::mem::replace(&mut timing.easing, "linear".to_string());
+        let keyframes = self.resolve_keyframes(name, &easing)?;
+        timing.end_time = timing.calculate_end_time();
+        let effect = AnimationEffect { target: Some(target.to_string()), keyframes, computed_timing: timing };
+        let id = self.insert(effect, AnimationClass::CssAnimation { name: name.to_string(), index, easing });
+        self.animations.get_mut(&id)?.play();
+        Some(id)
+    }
+    
+    /// Start a CSS transition of `property` on `target`
+    ///
+    /// Transitions started together share a generation, so call
+    /// [`Self::next_transition_generation`] once per style change.
+    pub fn start_css_transition(&mut self, target: &str, property: &str, from: &str, to: &str, mut timing: ComputedTiming) -> String {
+        let mut start = Keyframe::new(0.0);
+        start.set_property(property, from);
+        let mut end = Keyframe::new(1.0);
+        end.set_property(property, to);
+        timing.end_time = timing.calculate_end_time();
+        
+        let effect = AnimationEffect { target: Some(target.to_string()), keyframes: vec![start, end], computed_timing: timing };
+        let class = AnimationClass::CssTransition { generation: self.transition_generation, property: property.to_string() };
+        let id = self.insert(effect, class);
+        if let Some(animation) = self.animations.get_mut(&id) {
+            animation.play();
+        }
+        id
+    }
+    
+    /// Move on to the next transition generation
+    pub fn next_transition_generation(&mut self) {
+        self.transition_generation += 1;
+    }
+    
+    /// Playing animations of `target` in composite order, lowest first
+    pub fn effect_stack(&self, target: &str) -> Vec<&Animation> {
+        let mut stack: Vec<&Animation> = self.animations.values()
+            .filter(|a| a.play_state != PlayState::Idle)
+            .filter(|a| a.effect.as_ref().and_then(|e| e.target.as_deref()) == Some(target))
+            .collect();
+        stack.sort_by(|a, b| a.composite_cmp(b));
+        stack
+    }
+    
+    /// Animated value of `property` on `target` in one cascade origin,
+    /// [`CascadeLevel::ANIMATION`] or [`CascadeLevel::TRANSITION`]; the
+    /// highest effect in the stack replaces the ones below it
+    pub fn animated_value(&self, target: &str, property: &str, level: CascadeLevel) -> Option<AnimatableValue> {
+        self.effect_stack(target).into_iter().rev()
+            .filter(|a| a.class.cascade_level() == level)
+            .find_map(|a| a.sample(property))
+    }
+    
+    /// Cascaded value of `property` given the winning declared value:
+    /// transitions beat `!important` declarations, which beat animations,
+    /// which beat normal declarations
+    pub fn cascaded_value(&self, target: &str, property: &str, declared: Option<&str>, important: bool) -> Option<String> {
+        if let Some(value) = self.animated_value(target, property, CascadeLevel::TRANSITION) {
+            return Some(value.to_string());
+        }
+        if important && declared.is_some() {
+            return declared.map(str::to_string);
+        }
+        self.animated_value(target, property, CascadeLevel::ANIMATION)
+            .map(|value| value.to_string())
+            .or_else(|| declared.map(str::to_string))
+    }
+    
     /// Get animation by ID
     pub fn get(&self, id: &str) -> Option<&Animation> {
         self.animations.get(id)
@@ -511,6 +772,30 @@ impl DocumentAnimations {
     }
 }
 
+/// Keyframes of an `@keyframes` rule sorted by offset, merging those at the
+/// same offset with the same easing so later declarations win
+fn resolve_keyframes(rule: &KeyframesRule, default_easing: &str) -> Vec<Keyframe> {
+    let mut keyframes: Vec<Keyframe> = Vec::new();
+    for css in &rule.keyframes {
+        let offset = css.selector.offset();
+        let easing = css.easing.as_deref().unwrap_or(default_easing);
+        let index = match keyframes.iter().position(|kf| kf.offset == Some(offset) && kf.easing == easing) {
+            Some(index) => index,
+            None => {
+                let mut keyframe = Keyframe::new(offset);
+                keyframe.easing = easing.to_string();
+                keyframes.push(keyframe);
+                keyframes.len() - 1
+            }
+        };
+        for decl in &css.declarations {
+            keyframes[index].set_property(&decl.property, &decl.value);
+        }
+    }
+    keyframes.sort_by(|a, b| a.offset.partial_cmp(&b.offset).unwrap_or(Ordering::Equal));
+    keyframes
+}
+
 // ============================================================================
 // Keyframe Pre-Computation (Phase 4.2)
 // ============================================================================
@@ -616,10 +901,10 @@ impl KeyframePrecompute {
         
         match (before_value, after_value) {
             (Some(start), Some(end)) => {
-                Self::interpolate_values(start, end, local_progress)
+                Self::interpolate_values(property, start, end, local_progress)
             }
             (Some(v), None) | (None, Some(v)) => {
-                Self::parse_value(v)
+                Self::parse_value(property, v)
             }
             (None, None) => PrecomputedValue::String("".into()),
         }
@@ -666,41 +951,37 @@ impl KeyframePrecompute {
     }
     
     /// Interpolate between two values
-    fn interpolate_values(start: &str, end: &str, t: f64) -> PrecomputedValue {
-        // Try numeric interpolation
-        if let (Some((sv, su)), Some((ev, eu))) = (parse_length(start), parse_length(end)) {
-            if su == eu {
-                let value = sv + (ev - sv) * t;
-                return PrecomputedValue::Number(value, su);
-            }
-        }
-        
-        // Try color interpolation
-        if let (Some(sc), Some(ec)) = (parse_color(start), parse_color(end)) {
-            let r = lerp_u8(sc.0, ec.0, t);
-            let g = lerp_u8(sc.1, ec.1, t);
-            let b = lerp_u8(sc.2, ec.2, t);
-            let a = lerp_u8(sc.3, ec.3, t);
-            return PrecomputedValue::Color(r, g, b, a);
-        }
-        
-        // Fallback to discrete
-        if t < 0.5 {
-            Self::parse_value(start)
-        } else {
-            Self::parse_value(end)
-        }
+    fn interpolate_values(property: &str, start: &str, end: &str, t: f64) -> PrecomputedValue {
+        let start = AnimatableValue::parse(property, start);
+        let end = AnimatableValue::parse(property, end);
+        interpolate_property(property, &start, &end, t).into()
     }
     
     /// Parse a value string
-    fn parse_value(s: &str) -> PrecomputedValue {
-        if let Some((v, u)) = parse_length(s) {
-            return PrecomputedValue::Number(v, u);
-        }
-        if let Some(c) = parse_color(s) {
-            return PrecomputedValue::Color(c.0, c.1, c.2, c.3);
+    fn parse_value(property: &str, s: &str) -> PrecomputedValue {
+        AnimatableValue::parse(property, s).into()
+    }
+}
+
+impl From<AnimatableValue> for PrecomputedValue {
+    fn from(value: AnimatableValue) -> Self {
+        match value {
+            AnimatableValue::Length(v, u) => Self::Number(v, u),
+            AnimatableValue::Color(color) => {
+                let c = color.to_color();
+                Self::Color(c.r, c.g, c.b, c.a)
+            }
+            AnimatableValue::Transform(ref functions) => match transform_matrix(functions) {
+                Some([a, b, c, d, e, f]) => Self::Transform([
+                    a as f32, b as f32, 0.0, 0.0,
+                    c as f32, d as f32, 0.0, 0.0,
+                    0.0, 0.0, 1.0, 0.0,
+                    e as f32, f as f32, 0.0, 1.0,
+                ]),
+                None => Self::String(value.to_string().into()),
+            },
+            other => Self::String(other.to_string().into()),
         }
-        PrecomputedValue::String(s.into())
     }
 }
 
@@ -711,79 +992,6 @@ fn is_compositor_property(property: &str) -> bool {
     )
 }
 
-fn parse_length(s: &str) -> Option<(f64, Unit)> {
-    let s = s.trim();
-    
-    if let Some(v) = s.strip_suffix("px") {
-        return v.parse().ok().map(|n| (n, Unit::Px));
-    }
-    if let Some(v) = s.strip_suffix("em") {
-        return v.parse().ok().map(|n| (n, Unit::Em));
-    }
-    if let Some(v) = s.strip_suffix("rem") {
-        return v.parse().ok().map(|n| (n, Unit::Rem));
-    }
-    if let Some(v) = s.strip_suffix('%') {
-        return v.parse().ok().map(|n| (n, Unit::Percent));
-    }
-    if let Some(v) = s.strip_suffix("deg") {
-        return v.parse().ok().map(|n| (n, Unit::Deg));
-    }
-    
-    s.parse().ok().map(|n| (n, Unit::None))
-}
-
-fn parse_color(s: &str) -> Option<(u8, u8, u8, u8)> {
-    let s = s.trim();
-    
-    // Hex color
-    if s.starts_with('#') {
-        return parse_hex_color(s);
-    }
-    
-    // Named colors (basic)
-    match s {
-        "black" => Some((0, 0, 0, 255)),
-        "white" => Some((255, 255, 255, 255)),
-        "red" => Some((255, 0, 0, 255)),
-        "green" => Some((0, 128, 0, 255)),
-        "blue" => Some((0, 0, 255, 255)),
-        "transparent" => Some((0, 0, 0, 0)),
-        _ => None,
-    }
-}
-
-fn parse_hex_color(s: &str) -> Option<(u8, u8, u8, u8)> {
-    let s = s.strip_prefix('#')?;
-    
-    match s.len() {
-        3 => {
-            let r = u8::from_str_radix(&s[0..1], 16).ok()? * 17;
-            let g = u8::from_str_radix(&s[1..2], 16).ok()? * 17;
-            let b = u8::from_str_radix(&s[2..3], 16).ok()? * 17;
-            Some((r, g, b, 255))
-        }
-        6 => {
-            let r = u8::from_str_radix(&s[0..2], 16).ok()?;
-            let g = u8::from_str_radix(&s[2..4], 16).ok()?;
-            let b = u8::from_str_radix(&s[4..6], 16).ok()?;
-            Some((r, g, b, 255))
-        }
-        8 => {
-            let r = u8::from_str_radix(&s[0..2], 16).ok()?;
-            let g = u8::from_str_radix(&s[2..4], 16).ok()?;
-            let b = u8::from_str_radix(&s[4..6], 16).ok()?;
-            let a = u8::from_str_radix(&s[6..8], 16).ok()?;
-            Some((r, g, b, a))
-        }
-        _ => None,
-    }
-}
-
-fn lerp_u8(a: u8, b: u8, t: f64) -> u8 {
-    ((a as f64) + ((b as f64) - (a as f64)) * t).round() as u8
-}
-
 // ============================================================================
 // Compositor Thread Support (Phase 4.1)
 // ============================================================================
@@ -953,6 +1161,34 @@ mod tests {
         assert!(doc.get(&id).is_some());
     }
     
+    #[test]
+    fn test_css_animation_cascade() {
+        use crate::parallel_css_parser::{parse_stylesheet, Rule};
+        
+        let sheet = parse_stylesheet("test.css", "@keyframes grow { from { width: 0px } 50% { width: 50px; animation-timing-function: steps(1) } to { width: 100px } }");
+        let Rule::Keyframes(rule) = &sheet.rules[0] else { panic!("expected @keyframes") };
+        let mut doc = DocumentAnimations::new();
+        doc.register_keyframes(rule);
+        assert!(doc.start_css_animation("box", "missing", 0, ComputedTiming::default()).is_none());
+        
+        let timing = ComputedTiming { duration: 1000.0, ..Default::default() };
+        let id = doc.start_css_animation("box", "grow", 0, timing.clone()).unwrap();
+        doc.tick(250.0);
+        assert_eq!(doc.cascaded_value("box", "width", Some("10px"), false).as_deref(), Some("25px"));
+        doc.tick(500.0);
+        assert_eq!(doc.cascaded_value("box", "width", Some("10px"), false).as_deref(), Some("50px"));
+        assert_eq!(doc.cascaded_value("box", "width", Some("10px"), true).as_deref(), Some("10px"));
+        
+        // Transitions sit below animations in the stack but override both
+        // origins in the cascade
+        let script = doc.create(AnimationEffect { target: Some("box".into()), keyframes: vec![], computed_timing: timing.clone() });
+        doc.get_mut(&script).unwrap().play();
+        let transition = doc.start_css_transition("box", "width", "0px", "200px", timing);
+        let order: Vec<&str> = doc.effect_stack("box").iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(order, vec![transition.as_str(), id.as_str(), script.as_str()]);
+        assert_eq!(doc.cascaded_value("box", "width", Some("10px"), true).as_deref(), Some("0px"));
+    }
+    
     #[test]
     fn test_keyframe_precompute() {
         let mut kf1 = Keyframe::new(0.0);
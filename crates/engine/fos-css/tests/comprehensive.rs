@@ -240,3 +240,13 @@ fn test_parse_large_stylesheet() {
     println!("Large stylesheet rules: {}", stylesheet.len());
     assert_eq!(stylesheet.len(), 500);
 }
+
+#[test]
+fn test_serialize_declarations() {
+    assert_eq!(PropertyId::FontSize.name(), "font-size");
+    assert_eq!(PropertyId::ZIndex.name(), "z-index");
+    assert_eq!(PropertyValue::Keyword(Keyword::InlineBlock).to_css(), "inline-block");
+    assert_eq!(PropertyValue::Length(Length { value: 1.5, unit: LengthUnit::Em }).to_css(), "1.5em");
+    assert_eq!(PropertyValue::Length(Length::percent(50.0)).to_css(), "50%");
+    assert_eq!(PropertyValue::Color(Color::rgb(255, 0, 0)).to_css(), "rgb(255, 0, 0)");
+}
@@ -393,7 +393,10 @@ fn test_color_named_colors() {
 
 #[test]
 fn test_keyword_all_display_values() {
-    let keywords = ["none", "block", "inline", "inline-block", "flex", "grid", "contents"];
+    let keywords = [
+        "none", "block", "inline", "inline-block", "flow-root", "flex", "inline-flex",
+        "grid", "inline-grid", "contents",
+    ];
     for kw in &keywords {
         assert!(Keyword::from_str(kw).is_some(), "Keyword {} should parse", kw);
     }
@@ -245,6 +245,19 @@ impl<T> SmallVec<T> {
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
         SmallVecIterMut { vec: self, idx: 0 }
     }
+    
+    /// Keep only the items for which `keep` returns true, in order
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        let items = match std::mem::take(self) {
+            Self::Inline { data, .. } => data.into_iter().flatten().collect(),
+            Self::Heap(vec) => vec,
+        };
+        for item in items {
+            if keep(&item) {
+                self.push(item);
+            }
+        }
+    }
 }
 
 impl<T> Default for SmallVec<T> {
@@ -9,6 +9,7 @@
 //! - Slotchange event tracking
 //! - Declarative shadow DOM support
 
+use crate::custom_elements::CustomElementRegistry;
 use crate::NodeId;
 use std::sync::Arc;
 
@@ -20,6 +21,39 @@ pub enum ShadowRootMode {
     Closed,
 }
 
+impl ShadowRootMode {
+    /// Parse a mode keyword (ASCII case-insensitive, as for `shadowrootmode`)
+    pub fn from_str(s: &str) -> Option<Self> {
+        if s.eq_ignore_ascii_case("open") {
+            Some(Self::Open)
+        } else if s.eq_ignore_ascii_case("closed") {
+            Some(Self::Closed)
+        } else {
+            None
+        }
+    }
+
+    /// Convert to string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Closed => "closed",
+        }
+    }
+}
+
+/// Built-in elements that can host a shadow root
+const SHADOW_HOST_ELEMENTS: &[&str] = &[
+    "article", "aside", "blockquote", "body", "div", "footer",
+    "h1", "h2", "h3", "h4", "h5", "h6", "header", "main", "nav",
+    "p", "section", "span",
+];
+
+/// Whether an element with this local name can have a shadow root attached
+pub fn is_valid_shadow_host(local_name: &str) -> bool {
+    SHADOW_HOST_ELEMENTS.contains(&local_name) || CustomElementRegistry::is_valid_name(local_name)
+}
+
 /// Slot assignment mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum SlotAssignmentMode {
@@ -162,6 +196,22 @@ impl ShadowRoot {
         root
     }
 
+    /// Start tag of the `<template shadowrootmode>` that recreates this root
+    pub fn declarative_start_tag(&self) -> String {
+        let mut tag = format!("<template shadowrootmode=\"{}\"", self.mode.as_str());
+        if self.delegates_focus {
+            tag.push_str(" shadowrootdelegatesfocus=\"\"");
+        }
+        if self.serializable {
+            tag.push_str(" shadowrootserializable=\"\"");
+        }
+        if self.clonable {
+            tag.push_str(" shadowrootclonable=\"\"");
+        }
+        tag.push('>');
+        tag
+    }
+
     // --- Child management with COW ---
 
     /// Get children (shared reference)
@@ -474,6 +524,17 @@ mod tests {
         
         assert!(shadow.declarative);
         assert!(shadow.clonable);
+        assert_eq!(shadow.declarative_start_tag(), "<template shadowrootmode=\"open\" shadowrootclonable=\"\">");
+    }
+
+    #[test]
+    fn test_shadow_host_names() {
+        assert_eq!(ShadowRootMode::from_str("CLOSED"), Some(ShadowRootMode::Closed));
+        assert_eq!(ShadowRootMode::from_str("none"), None);
+        assert!(is_valid_shadow_host("div"));
+        assert!(is_valid_shadow_host("my-widget"));
+        assert!(!is_valid_shadow_host("img"));
+        assert!(!is_valid_shadow_host("font-face"));
     }
 
     #[test]
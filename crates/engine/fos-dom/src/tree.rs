@@ -6,7 +6,11 @@
 //! - O(1) node lookup by ID
 //! - Easy serialization/cloning
 
+use std::collections::HashMap;
+
 use crate::{Node, NodeId, NodeData, QualName, InternedString, StringInterner};
+use crate::node::SmallVec;
+use crate::shadow::{is_valid_shadow_host, ShadowError, ShadowRoot, ShadowRootInit};
 
 /// Arena-based DOM tree
 pub struct DomTree {
@@ -14,6 +18,9 @@ pub struct DomTree {
     pub nodes: Vec<Node>,
     /// String interner for deduplication
     interner: StringInterner,
+    /// Shadow roots by host element; shadow children are detached nodes
+    /// listed in their root
+    shadow_roots: HashMap<NodeId, ShadowRoot>,
 }
 
 impl DomTree {
@@ -22,6 +29,7 @@ impl DomTree {
         let mut tree = Self {
             nodes: Vec::with_capacity(256), // Pre-allocate for typical page
             interner: StringInterner::new(),
+            shadow_roots: HashMap::new(),
         };
         
         // Create document root at index 0
@@ -35,6 +43,7 @@ impl DomTree {
         let mut tree = Self {
             nodes: Vec::with_capacity(node_count),
             interner: StringInterner::new(),
+            shadow_roots: HashMap::new(),
         };
         tree.nodes.push(Node::document());
         tree
@@ -175,6 +184,92 @@ impl DomTree {
         }
     }
     
+    /// Set an attribute, keeping the cached id and class list in step
+    pub fn set_attribute(&mut self, node_id: NodeId, name: &str, value: &str) {
+        let local = self.interner.intern(name);
+        let id = (name == "id").then(|| self.interner.intern(value));
+        let classes: Vec<InternedString> = if name == "class" {
+            value.split_whitespace().map(|c| self.interner.intern(c)).collect()
+        } else {
+            Vec::new()
+        };
+        let Some(elem) = self.nodes.get_mut(node_id.index()).and_then(Node::as_element_mut) else { return };
+        match name {
+            "id" => elem.id = id,
+            "class" => {
+                elem.classes = SmallVec::new();
+                for class in classes {
+                    elem.classes.push(class);
+                }
+            }
+            _ => {}
+        }
+        elem.set_attr(QualName::new(InternedString::EMPTY, local), value.to_string());
+    }
+    
+    /// Remove an attribute
+    pub fn remove_attribute(&mut self, node_id: NodeId, name: &str) {
+        let Some(local) = self.interner.intern_lookup(name) else { return };
+        let Some(elem) = self.nodes.get_mut(node_id.index()).and_then(Node::as_element_mut) else { return };
+        match name {
+            "id" => elem.id = None,
+            "class" => elem.classes = SmallVec::new(),
+            _ => {}
+        }
+        elem.attrs.retain(|a| a.name.local != local);
+    }
+    
+    /// Replace the content of a text or comment node
+    pub fn set_text(&mut self, node_id: NodeId, content: &str) {
+        match self.nodes.get_mut(node_id.index()).map(|n| &mut n.data) {
+            Some(NodeData::Text(text)) => text.content = content.to_string(),
+            Some(NodeData::Comment(comment)) => *comment = content.to_string(),
+            _ => {}
+        }
+    }
+    
+    /// Attach a shadow root to an element (`attachShadow()`)
+    pub fn attach_shadow(&mut self, host: NodeId, init: ShadowRootInit) -> Result<&mut ShadowRoot, ShadowError> {
+        let elem = self.get(host).and_then(|n| n.as_element()).ok_or(ShadowError::NotSupported)?;
+        if !is_valid_shadow_host(self.interner.get(elem.name.local)) {
+            return Err(ShadowError::NotSupported);
+        }
+        if let Some(existing) = self.shadow_roots.get(&host) {
+            // A declarative root is handed over to the first matching call, emptied
+            if !existing.declarative || existing.mode != init.mode {
+                return Err(ShadowError::AlreadyAttached);
+            }
+            let children = existing.children().to_vec();
+            let root = self.shadow_roots.get_mut(&host).unwrap();
+            for child in children {
+                root.remove_child(child);
+            }
+            root.declarative = false;
+            return Ok(root);
+        }
+        Ok(self.shadow_roots.entry(host).or_insert(ShadowRoot::from_init(host, init)))
+    }
+    
+    /// Get the shadow root attached to an element
+    pub fn shadow_root(&self, host: NodeId) -> Option<&ShadowRoot> {
+        self.shadow_roots.get(&host)
+    }
+    
+    /// Get the shadow root attached to an element mutably
+    pub fn shadow_root_mut(&mut self, host: NodeId) -> Option<&mut ShadowRoot> {
+        self.shadow_roots.get_mut(&host)
+    }
+    
+    /// Append a child to the shadow root of `host`
+    pub fn append_shadow_child(&mut self, host: NodeId, child_id: NodeId) {
+        if let Some(root) = self.shadow_roots.get_mut(&host) {
+            root.append_child(child_id);
+            if let Some(child) = self.nodes.get_mut(child_id.index()) {
+                child.parent = NodeId::NONE;
+            }
+        }
+    }
+    
     /// Get string interner
     #[inline]
     pub fn interner(&self) -> &StringInterner {
@@ -266,6 +361,46 @@ mod tests {
         assert_eq!(tree.len(), 4);
     }
     
+    #[test]
+    fn test_edit_attributes_and_text() {
+        let mut tree = DomTree::new();
+        let div = tree.create_element("div");
+        let text = tree.create_text("old");
+        tree.append_child(div, text);
+        
+        tree.set_attribute(div, "class", "a b");
+        tree.set_attribute(div, "title", "t");
+        let elem = tree.get(div).unwrap().as_element().unwrap();
+        assert_eq!(elem.classes.len(), 2);
+        assert_eq!(elem.attrs.len(), 2);
+        
+        tree.remove_attribute(div, "class");
+        tree.set_text(text, "new");
+        let elem = tree.get(div).unwrap().as_element().unwrap();
+        assert!(elem.classes.is_empty());
+        assert_eq!(elem.attrs.iter().map(|a| a.value.as_str()).collect::<Vec<_>>(), ["t"]);
+        assert_eq!(tree.get(text).unwrap().as_text(), Some("new"));
+    }
+    
+    #[test]
+    fn test_attach_shadow() {
+        let mut tree = DomTree::new();
+        let host = tree.create_element("div");
+        let img = tree.create_element("img");
+        let text = tree.create_text("shadow");
+        
+        assert_eq!(tree.attach_shadow(img, ShadowRootInit::default()).err(), Some(ShadowError::NotSupported));
+        tree.attach_shadow(host, ShadowRootInit::default()).unwrap().declarative = true;
+        tree.append_shadow_child(host, text);
+        assert_eq!(tree.shadow_root(host).unwrap().children(), &[text]);
+        
+        // A declarative root is reused once, emptied
+        let init = ShadowRootInit { mode: crate::ShadowRootMode::Closed, ..Default::default() };
+        assert_eq!(tree.attach_shadow(host, init).err(), Some(ShadowError::AlreadyAttached));
+        assert!(tree.attach_shadow(host, ShadowRootInit::default()).unwrap().children().is_empty());
+        assert!(tree.attach_shadow(host, ShadowRootInit::default()).is_err());
+    }
+    
     #[test]
     fn test_memory_size() {
         // Verify Node is reasonably sized
@@ -6,6 +6,10 @@ use crate::interner::{InternedString, StringInterner};
 use std::fmt;
 use std::str::FromStr;
 
+pub mod public_suffix;
+
+pub use public_suffix::{PublicSuffixList, Site};
+
 /// URL parse error
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParseError {
@@ -529,6 +533,16 @@ impl Url {
         origin
     }
     
+    /// Get registrable domain (eTLD+1) of the host
+    pub fn registrable_domain(&self) -> Option<String> {
+        PublicSuffixList::current().registrable_domain(self.host_str()?)
+    }
+    
+    /// Get schemeful site (scheme://eTLD+1)
+    pub fn site(&self) -> Option<Site> {
+        Site::from_url(self)
+    }
+    
     /// Get host with port
     pub fn host_with_port(&self) -> String {
         let mut result = String::new();
@@ -0,0 +1,336 @@
+//! Public Suffix List
+//!
+//! Finds the public suffix (eTLD) and registrable domain (eTLD+1) of a host,
+//! following the rules of publicsuffix.org: plain rules, `*.` wildcards and
+//! `!` exceptions, with `*` as the implicit default rule.
+//!
+//! A list is compiled in; a newer one downloaded from publicsuffix.org can
+//! be installed at runtime and is picked up by every later lookup. Sites
+//! (scheme plus registrable domain) decide same-site for cookies and
+//! storage partitioning.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use super::{Host, Url};
+
+/// Suffixes bundled with the engine; a full list can be loaded with `parse`
+const BUILTIN_RULES: &str = "
+// Generic and country-code TLDs
+com
+net
+org
+edu
+gov
+mil
+int
+info
+biz
+io
+co
+dev
+app
+ai
+me
+tv
+us
+eu
+de
+fr
+nl
+es
+it
+ch
+se
+no
+ru
+ca
+br
+com.br
+cn
+com.cn
+in
+co.in
+uk
+co.uk
+org.uk
+ac.uk
+gov.uk
+ltd.uk
+plc.uk
+jp
+co.jp
+ne.jp
+or.jp
+ac.jp
+au
+com.au
+net.au
+org.au
+nz
+co.nz
+*.ck
+!www.ck
+*.kawasaki.jp
+!city.kawasaki.jp
+
+// Private domains that host unrelated sites
+github.io
+gitlab.io
+herokuapp.com
+appspot.com
+blogspot.com
+netlify.app
+vercel.app
+pages.dev
+workers.dev
+cloudfront.net
+s3.amazonaws.com
+";
+
+/// Number of lists installed since startup
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Parsed public suffix rules
+#[derive(Debug, Clone, Default)]
+pub struct PublicSuffixList {
+    rules: HashSet<String>,
+    /// Rules written `*.suffix`, stored without the wildcard label
+    wildcards: HashSet<String>,
+    /// Rules written `!domain`, stored without the `!`
+    exceptions: HashSet<String>,
+}
+
+impl PublicSuffixList {
+    /// Parse a list in the publicsuffix.org file format
+    pub fn parse(text: &str) -> Self {
+        let mut list = Self::default();
+        list.add_rules(text);
+        list
+    }
+
+    /// Add rules in the publicsuffix.org file format to this list
+    pub fn add_rules(&mut self, text: &str) {
+        for line in text.lines() {
+            let Some(rule) = line.split_whitespace().next() else { continue };
+            if rule.starts_with("//") {
+                continue;
+            }
+            let rule = rule.to_ascii_lowercase();
+            if let Some(exception) = rule.strip_prefix('!') {
+                self.exceptions.insert(exception.to_string());
+            } else if let Some(wildcard) = rule.strip_prefix("*.") {
+                self.wildcards.insert(wildcard.to_string());
+            } else {
+                self.rules.insert(rule);
+            }
+        }
+    }
+
+    /// The list bundled with the engine
+    pub fn builtin() -> &'static Self {
+        static LIST: OnceLock<PublicSuffixList> = OnceLock::new();
+        LIST.get_or_init(|| Self::parse(BUILTIN_RULES))
+    }
+
+    fn installed() -> &'static RwLock<Arc<PublicSuffixList>> {
+        static CURRENT: OnceLock<RwLock<Arc<PublicSuffixList>>> = OnceLock::new();
+        CURRENT.get_or_init(|| RwLock::new(Arc::new(Self::builtin().clone())))
+    }
+
+    /// The list in use: the builtin one unless an update was installed
+    pub fn current() -> Arc<Self> {
+        Self::installed().read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replace the list in use, e.g. with a freshly downloaded copy
+    pub fn install(list: Self) {
+        *Self::installed().write().unwrap_or_else(|e| e.into_inner()) = Arc::new(list);
+        GENERATION.fetch_add(1, Ordering::Release);
+    }
+
+    /// Bumped by every `install`, so indexes keyed by registrable domain
+    /// can tell they need rebuilding
+    pub fn generation() -> u64 {
+        GENERATION.load(Ordering::Acquire)
+    }
+
+    /// Go back to the builtin list
+    pub fn reset() {
+        Self::install(Self::builtin().clone());
+    }
+
+    /// Number of rules
+    pub fn len(&self) -> usize {
+        self.rules.len() + self.wildcards.len() + self.exceptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of labels in the public suffix of a host
+    fn suffix_labels(&self, labels: &[&str]) -> usize {
+        let n = labels.len();
+        let mut longest = 1;
+        for i in 0..n {
+            let candidate = labels[i..].join(".");
+            if self.exceptions.contains(&candidate) {
+                return n - i - 1;
+            }
+            if self.rules.contains(&candidate) {
+                longest = longest.max(n - i);
+            }
+            if i > 0 && self.wildcards.contains(&candidate) {
+                longest = longest.max(n - i + 1);
+            }
+        }
+        longest
+    }
+
+    /// Public suffix of a host; `None` for IP addresses and empty hosts
+    pub fn public_suffix(&self, host: &str) -> Option<String> {
+        let labels = Self::labels(host)?;
+        let len = self.suffix_labels(&labels);
+        Some(labels[labels.len() - len..].join("."))
+    }
+
+    /// Registrable domain (eTLD+1) of a host; `None` if the host is itself a
+    /// public suffix or an IP address
+    pub fn registrable_domain(&self, host: &str) -> Option<String> {
+        let labels = Self::labels(host)?;
+        let len = self.suffix_labels(&labels) + 1;
+        (labels.len() >= len).then(|| labels[labels.len() - len..].join("."))
+    }
+
+    /// Byte range of the registrable domain within `host`, for highlighting
+    /// the site in the address bar
+    pub fn registrable_domain_range(&self, host: &str) -> Option<Range<usize>> {
+        let domain = self.registrable_domain(host)?;
+        let end = host.trim_end_matches('.').len();
+        Some(end - domain.len()..end)
+    }
+
+    /// Whether two hosts share a registrable domain; hosts without one
+    /// (IP addresses, bare suffixes) are only same-site with themselves
+    pub fn is_same_site(&self, a: &str, b: &str) -> bool {
+        match (self.registrable_domain(a), self.registrable_domain(b)) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(&b),
+            _ => a.trim_end_matches('.').eq_ignore_ascii_case(b.trim_end_matches('.')),
+        }
+    }
+
+    /// Whether a host is exactly a public suffix
+    pub fn is_public_suffix(&self, host: &str) -> bool {
+        let Some(labels) = Self::labels(host) else { return false };
+        self.suffix_labels(&labels) == labels.len()
+    }
+
+    fn labels(host: &str) -> Option<Vec<&str>> {
+        let host = host.trim_end_matches('.');
+        let is_ip = host.contains(':') || host.split('.').all(|label| label.parse::<u8>().is_ok());
+        if host.is_empty() || is_ip {
+            return None;
+        }
+        Some(host.split('.').collect())
+    }
+}
+
+/// Schemeful site: a scheme and registrable domain
+///
+/// Hosts without a registrable domain (IP addresses, bare public suffixes)
+/// are their own site.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Site {
+    scheme: String,
+    host: String,
+}
+
+impl Site {
+    /// Site of a URL using the current list; `None` for URLs with an opaque
+    /// origin, such as `data:` URLs
+    pub fn from_url(url: &Url) -> Option<Self> {
+        Self::from_url_with(url, &PublicSuffixList::current())
+    }
+
+    /// Site of a URL using a given list
+    pub fn from_url_with(url: &Url, list: &PublicSuffixList) -> Option<Self> {
+        let host = match url.host()? {
+            Host::Domain(domain) => {
+                let domain = domain.to_ascii_lowercase();
+                list.registrable_domain(&domain)
+                    .unwrap_or_else(|| domain.trim_end_matches('.').to_string())
+            }
+            ip => ip.as_str(),
+        };
+        Some(Self { scheme: url.scheme().to_ascii_lowercase(), host })
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Registrable domain, or the whole host when it has none
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Whether two URLs are same-site; schemes must match as well
+    pub fn is_same_site(a: &Url, b: &Url) -> bool {
+        match (Self::from_url(a), Self::from_url(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Site {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}", self.scheme, self.host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registrable_domain() {
+        let psl = PublicSuffixList::builtin();
+        assert_eq!(psl.registrable_domain("www.example.com").as_deref(), Some("example.com"));
+        assert_eq!(psl.registrable_domain("a.b.example.co.uk").as_deref(), Some("example.co.uk"));
+        assert_eq!(psl.registrable_domain("user.github.io").as_deref(), Some("user.github.io"));
+        assert_eq!(psl.registrable_domain("co.uk"), None);
+        assert_eq!(psl.registrable_domain("192.168.0.1"), None);
+        // Unlisted TLDs fall back to the default `*` rule
+        assert_eq!(psl.registrable_domain("shop.example.zz").as_deref(), Some("example.zz"));
+    }
+
+    #[test]
+    fn test_wildcards_and_exceptions() {
+        let psl = PublicSuffixList::builtin();
+        assert_eq!(psl.public_suffix("foo.bar.ck").as_deref(), Some("bar.ck"));
+        assert!(psl.is_public_suffix("bar.ck"));
+        assert_eq!(psl.registrable_domain("www.ck").as_deref(), Some("www.ck"));
+        assert_eq!(psl.registrable_domain("a.city.kawasaki.jp").as_deref(), Some("city.kawasaki.jp"));
+    }
+
+    #[test]
+    fn test_sites() {
+        let url = |s| Url::parse(s).unwrap();
+        let site = Site::from_url_with(&url("https://mail.example.co.uk/inbox"), PublicSuffixList::builtin());
+        assert_eq!(site.unwrap().to_string(), "https://example.co.uk");
+        assert!(Site::is_same_site(&url("https://a.example.com"), &url("https://b.example.com:8443")));
+        assert!(!Site::is_same_site(&url("http://example.com"), &url("https://example.com")));
+        assert!(!Site::is_same_site(&url("https://alice.github.io"), &url("https://bob.github.io")));
+
+        let psl = PublicSuffixList::builtin();
+        let host = "news.bbc.co.uk";
+        assert_eq!(&host[psl.registrable_domain_range(host).unwrap()], "bbc.co.uk");
+        assert!(psl.is_same_site("192.168.0.1", "192.168.0.1"));
+        assert!(!psl.is_same_site("co.uk", "example.co.uk"));
+    }
+}
@@ -61,6 +61,12 @@ impl MemoryBudget {
             gpu_textures: 50 * 1024 * 1024,       // 50MB GPU
         }
     }
+    
+    /// Memory ceiling of a renderer hosting `tabs` tabs: `per_tab` for each,
+    /// never more than the system total
+    pub fn renderer_ceiling(&self, tabs: usize) -> usize {
+        self.per_tab.saturating_mul(tabs.max(1)).min(self.total_system)
+    }
 }
 
 /// Tab memory usage
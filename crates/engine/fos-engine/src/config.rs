@@ -1,5 +1,8 @@
 //! Engine Configuration
 
+use crate::budget::MemoryBudget;
+use crate::process::ProcessModel;
+
 /// Engine configuration options
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -14,6 +17,13 @@ pub struct Config {
     
     /// Maximum memory per page (bytes)
     pub max_memory: usize,
+    
+    /// How tabs are assigned to renderer processes
+    pub process_model: ProcessModel,
+    
+    /// Memory limits; each renderer is held to `per_tab` for every tab it
+    /// hosts
+    pub memory_budget: MemoryBudget,
 }
 
 impl Default for Config {
@@ -23,6 +33,8 @@ impl Default for Config {
             enable_images: true,
             user_agent: format!("fOS-Engine/{}", crate::VERSION),
             max_memory: 100 * 1024 * 1024, // 100MB
+            process_model: ProcessModel::default(),
+            memory_budget: MemoryBudget::default(),
         }
     }
 }
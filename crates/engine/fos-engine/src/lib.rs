@@ -95,7 +95,7 @@ pub use compat::{CompatibilityTester, CompatibilityReport, TestResult, FeatureCh
 
 // Architecture exports
 pub use process::{
-    ProcessArchitecture, ProcessType, ProcessId, ProcessState, ProcessArgs, TabId,
+    ProcessArchitecture, ProcessType, ProcessModel, ProcessId, ProcessState, ProcessArgs, TabId, OomAction,
     BrowserProcess, TabInfo, RendererProcess, NetworkProcess, GpuProcess, StorageProcess,
 };
 pub use ipc::{
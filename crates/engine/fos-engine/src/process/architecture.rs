@@ -8,9 +8,11 @@ use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicU32, Ordering};
 
 use super::{
-    ProcessId, ProcessState, ProcessType, TabId,
+    ProcessId, ProcessModel, ProcessState, ProcessType, TabId,
     BrowserProcess, RendererProcess, NetworkProcess, GpuProcess, StorageProcess,
 };
+use crate::budget::MemoryBudget;
+use crate::Config;
 
 /// Counter for generating unique process IDs
 static NEXT_PROCESS_ID: AtomicU32 = AtomicU32::new(1);
@@ -19,51 +21,108 @@ fn next_process_id() -> ProcessId {
     ProcessId::new(NEXT_PROCESS_ID.fetch_add(1, Ordering::SeqCst))
 }
 
+/// What to do about a renderer over its memory ceiling, in order of
+/// escalation, so that one heavy page never gets the whole browser killed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OomAction {
+    /// Within its ceiling
+    None,
+    /// Ask the renderer to drop its caches: decoded images, layout and
+    /// bytecode
+    PurgeCaches,
+    /// The tab was taken off its renderer and marked discarded; it reloads
+    /// when shown again
+    DiscardTab(TabId),
+}
+
 /// Multi-process browser architecture
 #[derive(Debug)]
 pub struct ProcessArchitecture {
     /// Main browser process state
     browser: BrowserProcess,
-    /// Renderer processes (one per tab)
-    renderers: HashMap<TabId, RendererProcess>,
+    /// Renderer processes
+    renderers: HashMap<ProcessId, RendererProcess>,
+    /// Renderer hosting each tab
+    tab_renderers: HashMap<TabId, ProcessId>,
+    /// Renderer of each site, under process-per-site
+    site_renderers: HashMap<String, ProcessId>,
     /// Network process (single, optional)
     network: Option<NetworkProcess>,
     /// GPU process (single, optional)
     gpu: Option<GpuProcess>,
     /// Storage process (single, optional)
     storage: Option<StorageProcess>,
-    /// Whether we're in single-process mode (for debugging)
-    single_process_mode: bool,
+    /// How tabs are assigned to renderers
+    model: ProcessModel,
+    /// Renderers run inside the browser process whatever the model
+    in_process: bool,
+    /// Memory limits renderer ceilings are taken from
+    budget: MemoryBudget,
 }
 
 impl ProcessArchitecture {
     /// Create a new process architecture
     pub fn new() -> Self {
-        Self {
-            browser: BrowserProcess::new(),
-            renderers: HashMap::new(),
-            network: None,
-            gpu: None,
-            storage: None,
-            single_process_mode: false,
-        }
+        Self::with_model(ProcessModel::default(), MemoryBudget::default())
     }
     
     /// Create in single-process mode (all in-process, for debugging)
     pub fn single_process() -> Self {
+        Self::with_model(ProcessModel::SingleProcess, MemoryBudget::default())
+    }
+    
+    /// Create with the process model and memory budget of an engine
+    /// configuration
+    pub fn from_config(config: &Config) -> Self {
+        Self::with_model(config.process_model, config.memory_budget.clone())
+    }
+    
+    /// Create with a process model and memory budget
+    pub fn with_model(model: ProcessModel, budget: MemoryBudget) -> Self {
         Self {
             browser: BrowserProcess::new(),
             renderers: HashMap::new(),
+            tab_renderers: HashMap::new(),
+            site_renderers: HashMap::new(),
             network: None,
             gpu: None,
             storage: None,
-            single_process_mode: true,
+            model,
+            in_process: false,
+            budget,
         }
     }
     
+    /// Run renderers inside the browser process whatever the model, for
+    /// embedders that render pages themselves; the model still decides
+    /// which tabs share a renderer and its memory ceiling
+    pub fn in_process(mut self) -> Self {
+        self.in_process = true;
+        self
+    }
+    
     /// Check if running in single-process mode
     pub fn is_single_process(&self) -> bool {
-        self.single_process_mode
+        self.in_process || self.model == ProcessModel::SingleProcess
+    }
+    
+    /// Get process model
+    pub fn process_model(&self) -> ProcessModel {
+        self.model
+    }
+    
+    /// Change the process model; tabs keep their renderer until they are
+    /// next assigned one
+    pub fn set_process_model(&mut self, model: ProcessModel) {
+        self.model = model;
+        if model != ProcessModel::ProcessPerSite {
+            self.site_renderers.clear();
+        }
+    }
+    
+    /// Get memory budget
+    pub fn budget(&self) -> &MemoryBudget {
+        &self.budget
     }
     
     /// Get browser process
@@ -76,13 +135,52 @@ impl ProcessArchitecture {
         &mut self.browser
     }
     
-    /// Spawn a new renderer process for a tab
+    /// Spawn a new renderer process for a tab, replacing the one it had
     pub fn spawn_renderer(&mut self, tab: TabId) -> io::Result<()> {
-        if self.single_process_mode {
-            // In single-process mode, create in-process renderer
-            let renderer = RendererProcess::in_process(next_process_id(), tab);
-            self.renderers.insert(tab, renderer);
-            return Ok(());
+        self.detach_tab(tab)?;
+        let renderer = self.launch_renderer(tab)?;
+        let process = renderer.id();
+        self.renderers.insert(process, renderer);
+        self.attach_tab(tab, process);
+        Ok(())
+    }
+    
+    /// Give a tab a renderer for `url`: under process-per-site, the running
+    /// renderer of the URL's site if there is one; otherwise a new renderer
+    pub fn assign_renderer(&mut self, tab: TabId, url: &str) -> io::Result<ProcessId> {
+        if let Some(info) = self.browser.get_tab_mut(tab) {
+            info.discarded = false;
+        }
+        
+        let site = match self.model {
+            ProcessModel::ProcessPerSite => site_of(url),
+            _ => None,
+        };
+        let Some(site) = site else {
+            self.spawn_renderer(tab)?;
+            return Ok(self.tab_renderers[&tab]);
+        };
+        
+        let shared = self.site_renderers.get(&site).copied()
+            .filter(|p| self.renderers.get(p).is_some_and(RendererProcess::is_running));
+        if let Some(process) = shared {
+            if self.tab_renderers.get(&tab) != Some(&process) {
+                self.detach_tab(tab)?;
+                self.attach_tab(tab, process);
+            }
+            return Ok(process);
+        }
+        
+        self.spawn_renderer(tab)?;
+        let process = self.tab_renderers[&tab];
+        self.site_renderers.insert(site, process);
+        Ok(process)
+    }
+    
+    /// Start a renderer process, in-process in single-process mode
+    fn launch_renderer(&self, tab: TabId) -> io::Result<RendererProcess> {
+        if self.is_single_process() {
+            return Ok(RendererProcess::in_process(next_process_id(), tab));
         }
         
         // Spawn actual process
@@ -99,33 +197,106 @@ impl ProcessArchitecture {
             .spawn()?;
         
         let process_id = ProcessId::new(child.id());
-        let renderer = RendererProcess::from_child(process_id, tab, child, ipc_path);
-        self.renderers.insert(tab, renderer);
-        
+        Ok(RendererProcess::from_child(process_id, tab, child, ipc_path))
+    }
+    
+    /// Host a tab on a renderer and raise the renderer's ceiling for it
+    fn attach_tab(&mut self, tab: TabId, process: ProcessId) {
+        self.tab_renderers.insert(tab, process);
+        self.update_ceiling(process);
+    }
+    
+    /// Take a tab off its renderer, terminating the renderer once it hosts
+    /// no tabs
+    fn detach_tab(&mut self, tab: TabId) -> io::Result<()> {
+        let Some(process) = self.tab_renderers.remove(&tab) else {
+            return Ok(());
+        };
+        if self.tab_renderers.values().any(|p| *p == process) {
+            self.update_ceiling(process);
+            return Ok(());
+        }
+        self.site_renderers.retain(|_, p| *p != process);
+        if let Some(mut renderer) = self.renderers.remove(&process) {
+            renderer.terminate()?;
+        }
         Ok(())
     }
     
+    fn update_ceiling(&mut self, process: ProcessId) {
+        let ceiling = self.budget.renderer_ceiling(self.hosted_tabs(process).len());
+        if let Some(renderer) = self.renderers.get_mut(&process) {
+            renderer.set_memory_ceiling(ceiling);
+        }
+    }
+    
+    /// Tabs hosted by a renderer, in tab order
+    pub fn hosted_tabs(&self, process: ProcessId) -> Vec<TabId> {
+        let mut tabs: Vec<TabId> = self.tab_renderers.iter()
+            .filter(|(_, p)| **p == process)
+            .map(|(tab, _)| *tab)
+            .collect();
+        tabs.sort_by_key(|t| t.0);
+        tabs
+    }
+    
     /// Get renderer for a tab
     pub fn get_renderer(&self, tab: TabId) -> Option<&RendererProcess> {
-        self.renderers.get(&tab)
+        self.renderers.get(self.tab_renderers.get(&tab)?)
     }
     
     /// Get mutable renderer for a tab
     pub fn get_renderer_mut(&mut self, tab: TabId) -> Option<&mut RendererProcess> {
-        self.renderers.get_mut(&tab)
+        self.renderers.get_mut(self.tab_renderers.get(&tab)?)
     }
     
-    /// Terminate renderer for a tab
+    /// Take a tab off its renderer; the renderer is terminated once no tab
+    /// uses it
     pub fn terminate_renderer(&mut self, tab: TabId) -> io::Result<()> {
-        if let Some(mut renderer) = self.renderers.remove(&tab) {
-            renderer.terminate()?;
+        self.detach_tab(tab)
+    }
+    
+    /// Forget a closed tab and release its renderer
+    pub fn close_tab(&mut self, tab: TabId) -> io::Result<()> {
+        self.browser.close_tab(tab);
+        self.detach_tab(tab)
+    }
+    
+    /// Record the memory use of a tab's renderer and enforce its ceiling:
+    /// the first time it goes over, its caches are purged; if it is still
+    /// over after that, its tabs are discarded one per report, background
+    /// tabs before the focused one, oldest first
+    pub fn report_renderer_memory(&mut self, tab: TabId, bytes: usize) -> io::Result<OomAction> {
+        let Some(&process) = self.tab_renderers.get(&tab) else {
+            return Ok(OomAction::None);
+        };
+        let Some(renderer) = self.renderers.get_mut(&process) else {
+            return Ok(OomAction::None);
+        };
+        renderer.set_memory_usage(bytes);
+        if !renderer.is_over_ceiling() {
+            renderer.set_caches_purged(false);
+            return Ok(OomAction::None);
         }
-        Ok(())
+        if !renderer.caches_purged() {
+            renderer.set_caches_purged(true);
+            return Ok(OomAction::PurgeCaches);
+        }
+        
+        let focused = self.browser.focused_tab();
+        let Some(victim) = self.hosted_tabs(process).into_iter().min_by_key(|t| (Some(*t) == focused, t.0)) else {
+            return Ok(OomAction::None);
+        };
+        self.detach_tab(victim)?;
+        if let Some(info) = self.browser.get_tab_mut(victim) {
+            info.discarded = true;
+        }
+        Ok(OomAction::DiscardTab(victim))
     }
     
     /// Spawn network process
     pub fn spawn_network(&mut self) -> io::Result<()> {
-        if self.single_process_mode {
+        if self.is_single_process() {
             self.network = Some(NetworkProcess::in_process(next_process_id()));
             return Ok(());
         }
@@ -154,7 +325,7 @@ impl ProcessArchitecture {
     
     /// Spawn GPU process
     pub fn spawn_gpu(&mut self) -> io::Result<()> {
-        if self.single_process_mode {
+        if self.is_single_process() {
             self.gpu = Some(GpuProcess::in_process(next_process_id()));
             return Ok(());
         }
@@ -183,7 +354,7 @@ impl ProcessArchitecture {
     
     /// Spawn storage process
     pub fn spawn_storage(&mut self) -> io::Result<()> {
-        if self.single_process_mode {
+        if self.is_single_process() {
             self.storage = Some(StorageProcess::in_process(next_process_id()));
             return Ok(());
         }
@@ -212,7 +383,7 @@ impl ProcessArchitecture {
     
     /// Get all renderer tab IDs
     pub fn renderer_tabs(&self) -> impl Iterator<Item = TabId> + '_ {
-        self.renderers.keys().copied()
+        self.tab_renderers.keys().copied()
     }
     
     /// Count active renderers
@@ -223,9 +394,10 @@ impl ProcessArchitecture {
     /// Shutdown all processes
     pub fn shutdown(&mut self) -> io::Result<()> {
         // Terminate all renderers
-        let tabs: Vec<TabId> = self.renderers.keys().copied().collect();
-        for tab in tabs {
-            let _ = self.terminate_renderer(tab);
+        self.tab_renderers.clear();
+        self.site_renderers.clear();
+        for (_, mut renderer) in self.renderers.drain() {
+            let _ = renderer.terminate();
         }
         
         // Terminate service processes
@@ -243,6 +415,13 @@ impl ProcessArchitecture {
     }
 }
 
+/// Site (scheme and registrable domain) of a URL; `None` for URLs with an
+/// opaque origin, which never share a renderer
+fn site_of(url: &str) -> Option<String> {
+    let url = fos_dom::url::Url::parse(url).ok()?;
+    Some(url.site()?.to_string())
+}
+
 impl Default for ProcessArchitecture {
     fn default() -> Self {
         Self::new()
@@ -286,6 +465,67 @@ mod tests {
         assert!(arch.storage().is_some());
     }
     
+    #[test]
+    fn test_process_per_site() {
+        let mut arch = ProcessArchitecture::with_model(ProcessModel::ProcessPerSite, MemoryBudget::default()).in_process();
+        let (a, b, c) = (TabId::new(1), TabId::new(2), TabId::new(3));
+        let docs = arch.assign_renderer(a, "https://docs.example.com/").unwrap();
+        assert_eq!(arch.assign_renderer(b, "https://www.example.com/a").unwrap(), docs);
+        let other = arch.assign_renderer(c, "https://other.org/").unwrap();
+        assert_ne!(other, docs);
+        assert_eq!(arch.renderer_count(), 2);
+        assert_eq!(arch.hosted_tabs(docs), vec![a, b]);
+        assert_eq!(arch.get_renderer(b).unwrap().memory_ceiling(), 2 * arch.budget().per_tab);
+        
+        // Navigating away from the site leaves the shared renderer
+        assert_eq!(arch.assign_renderer(b, "https://other.org/x").unwrap(), other);
+        assert_eq!(arch.hosted_tabs(docs), vec![a]);
+        arch.terminate_renderer(a).unwrap();
+        assert_eq!(arch.renderer_count(), 1);
+    }
+    
+    #[test]
+    fn test_embedder_tabs() {
+        let mut arch = ProcessArchitecture::with_model(ProcessModel::ProcessPerSite, MemoryBudget::default()).in_process();
+        assert!(arch.is_single_process());
+        let (a, b) = (TabId::new(4), TabId::new(9));
+        arch.browser_mut().add_tab(a);
+        arch.browser_mut().add_tab(b);
+        let shared = arch.assign_renderer(a, "https://example.com/").unwrap();
+        assert_eq!(arch.assign_renderer(b, "https://example.com/b").unwrap(), shared);
+        
+        // Under process-per-tab, tabs leave the shared renderer as they navigate
+        arch.set_process_model(ProcessModel::ProcessPerTab);
+        assert_ne!(arch.assign_renderer(b, "https://example.com/c").unwrap(), shared);
+        assert_eq!(arch.renderer_count(), 2);
+        arch.close_tab(a).unwrap();
+        assert!(arch.browser().get_tab(a).is_none());
+        assert_eq!(arch.renderer_count(), 1);
+    }
+    
+    #[test]
+    fn test_memory_ceiling() {
+        let mut arch = ProcessArchitecture::single_process();
+        let background = arch.browser_mut().create_tab();
+        let focused = arch.browser_mut().create_tab();
+        arch.browser_mut().set_focused_tab(focused);
+        arch.spawn_renderer(background).unwrap();
+        arch.spawn_renderer(focused).unwrap();
+        let ceiling = arch.budget().per_tab;
+        
+        assert_eq!(arch.report_renderer_memory(background, ceiling).unwrap(), OomAction::None);
+        assert_eq!(arch.report_renderer_memory(background, ceiling + 1).unwrap(), OomAction::PurgeCaches);
+        assert_eq!(arch.report_renderer_memory(background, ceiling + 1).unwrap(), OomAction::DiscardTab(background));
+        assert!(arch.get_renderer(background).is_none());
+        assert!(arch.browser().get_tab(background).unwrap().discarded);
+        assert!(arch.get_renderer(focused).is_some());
+        
+        // Going back under the ceiling rearms the cache purge
+        assert_eq!(arch.report_renderer_memory(focused, ceiling + 1).unwrap(), OomAction::PurgeCaches);
+        assert_eq!(arch.report_renderer_memory(focused, 1).unwrap(), OomAction::None);
+        assert_eq!(arch.report_renderer_memory(focused, ceiling + 1).unwrap(), OomAction::PurgeCaches);
+    }
+    
     #[test]
     fn test_terminate_renderer() {
         let mut arch = ProcessArchitecture::single_process();
@@ -39,6 +39,8 @@ pub struct TabInfo {
     pub pinned: bool,
     /// Is muted
     pub muted: bool,
+    /// Discarded to reclaim memory; reloads when shown again
+    pub discarded: bool,
 }
 
 impl TabInfo {
@@ -50,6 +52,7 @@ impl TabInfo {
             loading: false,
             pinned: false,
             muted: false,
+            discarded: false,
         }
     }
 }
@@ -98,6 +101,16 @@ impl BrowserProcess {
         id
     }
     
+    /// Track a tab whose ID the embedder chose; tabs created afterwards get
+    /// higher IDs
+    pub fn add_tab(&mut self, id: TabId) -> &mut TabInfo {
+        self.next_tab_id = self.next_tab_id.max(id.0 + 1);
+        if self.focused_tab.is_none() {
+            self.focused_tab = Some(id);
+        }
+        self.tabs.entry(id).or_insert_with(|| TabInfo::new(id))
+    }
+    
     /// Close a tab
     pub fn close_tab(&mut self, id: TabId) -> bool {
         if self.tabs.remove(&id).is_some() {
@@ -196,6 +209,16 @@ mod tests {
         assert_eq!(browser.focused_tab(), Some(tab1));
     }
     
+    #[test]
+    fn test_add_tab() {
+        let mut browser = BrowserProcess::new();
+        
+        browser.add_tab(TabId::new(7)).url = "https://example.com".into();
+        assert_eq!(browser.add_tab(TabId::new(7)).url, "https://example.com");
+        assert_eq!(browser.tab_count(), 1);
+        assert_eq!(browser.create_tab(), TabId::new(8));
+    }
+    
     #[test]
     fn test_close_tab() {
         let mut browser = BrowserProcess::new();
@@ -12,7 +12,7 @@ use super::{ProcessId, ProcessState, TabId};
 pub struct RendererProcess {
     /// Process ID
     id: ProcessId,
-    /// Associated tab
+    /// Tab the renderer was spawned for
     tab: TabId,
     /// Current state
     state: ProcessState,
@@ -22,6 +22,10 @@ pub struct RendererProcess {
     ipc_path: Option<String>,
     /// Memory usage estimate (bytes)
     memory_usage: usize,
+    /// Memory ceiling (bytes), 0 for none
+    memory_ceiling: usize,
+    /// Caches were purged since the renderer last went over its ceiling
+    caches_purged: bool,
     /// Is in-process (single-process mode)
     in_process: bool,
 }
@@ -36,6 +40,8 @@ impl RendererProcess {
             child: None,
             ipc_path: None,
             memory_usage: 0,
+            memory_ceiling: 0,
+            caches_purged: false,
             in_process: true,
         }
     }
@@ -49,6 +55,8 @@ impl RendererProcess {
             child: Some(child),
             ipc_path: Some(ipc_path),
             memory_usage: 0,
+            memory_ceiling: 0,
+            caches_purged: false,
             in_process: false,
         }
     }
@@ -58,7 +66,7 @@ impl RendererProcess {
         self.id
     }
     
-    /// Get associated tab
+    /// Get the tab the renderer was spawned for
     pub fn tab(&self) -> TabId {
         self.tab
     }
@@ -93,6 +101,32 @@ impl RendererProcess {
         self.memory_usage = bytes;
     }
     
+    /// Get memory ceiling
+    pub fn memory_ceiling(&self) -> usize {
+        self.memory_ceiling
+    }
+    
+    /// Set memory ceiling; 0 for none
+    pub fn set_memory_ceiling(&mut self, bytes: usize) {
+        self.memory_ceiling = bytes;
+    }
+    
+    /// Whether memory usage exceeds the ceiling
+    pub fn is_over_ceiling(&self) -> bool {
+        self.memory_ceiling != 0 && self.memory_usage > self.memory_ceiling
+    }
+    
+    /// Whether caches were purged since the renderer went over its ceiling
+    pub fn caches_purged(&self) -> bool {
+        self.caches_purged
+    }
+    
+    /// Note that the renderer purged its caches, or that it is back under
+    /// its ceiling
+    pub fn set_caches_purged(&mut self, purged: bool) {
+        self.caches_purged = purged;
+    }
+    
     /// Check if process is still running
     pub fn is_running(&self) -> bool {
         if self.in_process {
@@ -87,6 +87,40 @@ impl fmt::Display for ProcessType {
     }
 }
 
+/// How tabs are assigned to renderer processes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ProcessModel {
+    /// Tabs showing the same site (scheme and registrable domain) share a
+    /// renderer; different sites never do
+    ProcessPerSite,
+    /// Every tab gets its own renderer
+    #[default]
+    ProcessPerTab,
+    /// Renderers run inside the browser process, for embedded use
+    SingleProcess,
+}
+
+impl ProcessModel {
+    /// Parse from command-line argument
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "process-per-site" => Some(Self::ProcessPerSite),
+            "process-per-tab" => Some(Self::ProcessPerTab),
+            "single-process" => Some(Self::SingleProcess),
+            _ => None,
+        }
+    }
+    
+    /// Convert to command-line argument
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            Self::ProcessPerSite => "process-per-site",
+            Self::ProcessPerTab => "process-per-tab",
+            Self::SingleProcess => "single-process",
+        }
+    }
+}
+
 /// Process state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcessState {
@@ -3,7 +3,10 @@
 //! Parse HTML incrementally without blocking on full document arrival.
 //! Enables streaming render of above-the-fold content while rest loads.
 
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+
+use fos_dom::shadow::is_valid_shadow_host;
+use fos_dom::ShadowRootMode;
 
 /// Incremental parser state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -594,8 +597,10 @@ pub struct IncrementalParser {
     tokenizer: IncrementalTokenizer,
     /// Current phase
     phase: ParsePhase,
-    /// Open elements stack (tag names)
-    open_elements: Vec<String>,
+    /// Open elements stack
+    open_elements: Vec<OpenElement>,
+    /// Hosts that already received a declarative shadow root
+    shadow_hosts: HashSet<u32>,
     /// Node ID counter
     next_id: u32,
     /// Parsed nodes ready for DOM construction
@@ -604,6 +609,15 @@ pub struct IncrementalParser {
     stats: IncrementalParseStats,
 }
 
+/// Entry of the open elements stack
+#[derive(Debug)]
+struct OpenElement {
+    name: String,
+    id: u32,
+    /// A `<template shadowrootmode>` emitted as a shadow root
+    shadow_root: bool,
+}
+
 /// Parsed node ready for DOM construction
 #[derive(Debug, Clone)]
 pub struct ParsedNode {
@@ -625,6 +639,14 @@ pub enum ParsedNodeContent {
         tag: String,
         attributes: Vec<(String, String)>,
     },
+    /// Declarative shadow root for the parent element; replaces its
+    /// `<template shadowrootmode>` and parents the template's contents
+    ShadowRoot {
+        mode: ShadowRootMode,
+        delegates_focus: bool,
+        clonable: bool,
+        serializable: bool,
+    },
     /// Text content
     Text(String),
     /// Comment
@@ -659,6 +681,7 @@ impl IncrementalParser {
             tokenizer: IncrementalTokenizer::new(),
             phase: ParsePhase::Initial,
             open_elements: Vec::new(),
+            shadow_hosts: HashSet::new(),
             next_id: 0,
             parsed_nodes: VecDeque::new(),
             stats: IncrementalParseStats::default(),
@@ -764,25 +787,46 @@ impl IncrementalParser {
                 let id = self.alloc_id();
                 let parent_id = self.current_parent();
                 
+                // The shadow root is emitted on the start tag, so it is attached
+                // before any later script in the stream runs
+                let host = if name == "template" { self.declarative_shadow_host(&attributes) } else { None };
+                let shadow_root = host.is_some();
+                let content = if shadow_root {
+                    let has = |n: &str| attributes.iter().any(|(a, _)| a == n);
+                    let mode = attributes.iter()
+                        .find(|(a, _)| a == "shadowrootmode")
+                        .and_then(|(_, v)| ShadowRootMode::from_str(v))
+                        .unwrap_or_default();
+                    ParsedNodeContent::ShadowRoot {
+                        mode,
+                        delegates_focus: has("shadowrootdelegatesfocus"),
+                        clonable: has("shadowrootclonable"),
+                        serializable: has("shadowrootserializable"),
+                    }
+                } else {
+                    ParsedNodeContent::Element {
+                        tag: name.clone(),
+                        attributes,
+                    }
+                };
+                self.shadow_hosts.extend(host);
+                
                 self.parsed_nodes.push_back(ParsedNode {
                     id,
                     parent_id,
-                    content: ParsedNodeContent::Element {
-                        tag: name.clone(),
-                        attributes,
-                    },
+                    content,
                 });
                 self.stats.nodes_created += 1;
                 
                 // Push to stack if not void element
                 if !self_closing && !is_void_element(&name) {
-                    self.open_elements.push(name);
+                    self.open_elements.push(OpenElement { name, id, shadow_root });
                 }
             }
             
             Token::EndTag { name } => {
                 // Pop matching element
-                if let Some(pos) = self.open_elements.iter().rposition(|e| e == &name) {
+                if let Some(pos) = self.open_elements.iter().rposition(|e| e.name == name) {
                     self.open_elements.truncate(pos);
                 }
             }
@@ -824,12 +868,20 @@ impl IncrementalParser {
     }
     
     fn current_parent(&self) -> Option<u32> {
-        // Simplified: just return current depth as parent
-        if self.open_elements.is_empty() {
-            None
-        } else {
-            Some(self.open_elements.len() as u32 - 1)
+        self.open_elements.last().map(|e| e.id)
+    }
+    
+    /// Host for a `<template>` start tag with these attributes, if it
+    /// declares a shadow root: a valid mode, inside an element that can
+    /// host one and hasn't got one yet (otherwise it's a plain template)
+    fn declarative_shadow_host(&self, attributes: &[(String, String)]) -> Option<u32> {
+        let mode = attributes.iter().find(|(a, _)| a == "shadowrootmode")?;
+        ShadowRootMode::from_str(&mode.1)?;
+        let parent = self.open_elements.last()?;
+        if parent.shadow_root || !is_valid_shadow_host(&parent.name) || self.shadow_hosts.contains(&parent.id) {
+            return None;
         }
+        Some(parent.id)
     }
 }
 
@@ -920,6 +972,32 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_declarative_shadow_streaming() {
+        let mut parser = IncrementalParser::new();
+        // Template tag split across chunks
+        parser.feed(b"<body><div><templ");
+        parser.process_all();
+        parser.feed(b"ate shadowrootmode=\"open\" shadowrootdelegatesfocus><span></span>");
+        parser.feed(b"<template shadowrootmode=open></template></template>");
+        parser.feed(b"<template shadowrootmode=open></template></div>");
+        parser.finish();
+        
+        let nodes: Vec<_> = std::iter::from_fn(|| parser.next_node()).collect();
+        let div = nodes.iter().find(|n| matches!(&n.content, ParsedNodeContent::Element { tag, .. } if tag == "div")).unwrap();
+        let root = &nodes[2];
+        assert_eq!(root.parent_id, Some(div.id));
+        assert!(matches!(root.content, ParsedNodeContent::ShadowRoot { mode: ShadowRootMode::Open, delegates_focus: true, .. }));
+        assert_eq!(nodes[3].parent_id, Some(root.id));
+        
+        // Nested in the shadow root, and a second one on the same host: plain templates
+        let templates: Vec<_> = nodes.iter()
+            .filter(|n| matches!(&n.content, ParsedNodeContent::Element { tag, .. } if tag == "template"))
+            .map(|n| n.parent_id)
+            .collect();
+        assert_eq!(templates, vec![Some(root.id), Some(div.id)]);
+    }
+    
     #[test]
     fn test_tokenizer_attributes() {
         let mut tokenizer = IncrementalTokenizer::new();
@@ -3,7 +3,7 @@
 //! Uses html5ever's build-in RcDom and converts to our DOM format.
 //! This is simpler and more reliable than implementing TreeSink directly.
 
-use fos_dom::{Document, DomTree, Node, NodeId, NodeData, ElementData, QualName};
+use fos_dom::{Document, DomTree, Node, NodeId, NodeData, ElementData, QualName, ShadowRootInit, ShadowRootMode};
 use html5ever::parse_document;
 use html5ever::tendril::TendrilSink;
 use markup5ever_rcdom::{RcDom, Handle, NodeData as RcNodeData};
@@ -34,7 +34,7 @@ impl HtmlParser {
         
         // Convert RcDom to our DOM
         let mut document = Document::empty(url);
-        self.convert_node(&dom.document, document.tree_mut(), NodeId::ROOT);
+        self.convert_children(&dom.document, document.tree_mut(), NodeId::ROOT);
         
         // Find html, head, body elements
         document.finalize();
@@ -43,18 +43,25 @@ impl HtmlParser {
         document
     }
     
-    /// Convert an RcDom node to our DOM format
-    fn convert_node(&self, handle: &Handle, tree: &mut DomTree, parent: NodeId) {
+    /// Convert the children of an RcDom node, appending them to `parent`
+    fn convert_children(&self, handle: &Handle, tree: &mut DomTree, parent: NodeId) {
+        for child in handle.children.borrow().iter() {
+            if self.attach_declarative_shadow(child, tree, parent) {
+                continue;
+            }
+            if let Some(id) = self.convert_node(child, tree) {
+                tree.append_child(parent, id);
+            }
+        }
+    }
+    
+    /// Convert an RcDom node to our DOM format, returning the detached node
+    fn convert_node(&self, handle: &Handle, tree: &mut DomTree) -> Option<NodeId> {
         // Handle is Rc<Node>, access data directly
         let node_data = &handle.data;
         
         match node_data {
-            RcNodeData::Document => {
-                // Document node - just process children
-                for child in handle.children.borrow().iter() {
-                    self.convert_node(child, tree, parent);
-                }
-            }
+            RcNodeData::Document => None,
             RcNodeData::Doctype { name, public_id, system_id } => {
                 let name_str = name.to_string();
                 let name_interned = tree.interner_mut().intern(&name_str);
@@ -72,18 +79,17 @@ impl HtmlParser {
                         system_id: system_id.to_string(),
                     },
                 });
-                tree.append_child(parent, id);
+                Some(id)
             }
             RcNodeData::Text { contents } => {
                 let text = contents.borrow().to_string();
-                if !text.trim().is_empty() {
-                    let id = tree.create_text(&text);
-                    tree.append_child(parent, id);
+                if text.trim().is_empty() {
+                    return None;
                 }
+                Some(tree.create_text(&text))
             }
             RcNodeData::Comment { contents } => {
-                let id = tree.create_comment(&contents.to_string());
-                tree.append_child(parent, id);
+                Some(tree.create_comment(&contents.to_string()))
             }
             RcNodeData::Element { name, attrs, .. } => {
                 // Create element
@@ -121,18 +127,58 @@ impl HtmlParser {
                     next_sibling: NodeId::NONE,
                     data: NodeData::Element(elem),
                 });
-                tree.append_child(parent, id);
                 
                 // Process children
-                for child in handle.children.borrow().iter() {
-                    self.convert_node(child, tree, id);
-                }
+                self.convert_children(handle, tree, id);
+                Some(id)
             }
             RcNodeData::ProcessingInstruction { .. } => {
                 // Ignore processing instructions for now
+                None
             }
         }
     }
+    
+    /// Declarative shadow DOM: a `<template shadowrootmode>` child attaches a
+    /// shadow root to `host` holding the template's contents, so the root
+    /// exists before any script sees the host. Returns false when the
+    /// template should stay a plain template (no valid mode, a host that
+    /// can't have a shadow root, or one that already has one).
+    fn attach_declarative_shadow(&self, handle: &Handle, tree: &mut DomTree, host: NodeId) -> bool {
+        let RcNodeData::Element { name, attrs, template_contents, .. } = &handle.data else {
+            return false;
+        };
+        if name.local.as_ref() != "template" || tree.shadow_root(host).is_some() {
+            return false;
+        }
+        let attrs = attrs.borrow();
+        let attr = |n: &str| attrs.iter().find(|a| a.name.local.as_ref() == n).map(|a| a.value.to_string());
+        let Some(mode) = attr("shadowrootmode").and_then(|m| ShadowRootMode::from_str(&m)) else {
+            return false;
+        };
+        let init = ShadowRootInit {
+            mode,
+            delegates_focus: attr("shadowrootdelegatesfocus").is_some(),
+            clonable: attr("shadowrootclonable").is_some(),
+            serializable: attr("shadowrootserializable").is_some(),
+            ..Default::default()
+        };
+        match tree.attach_shadow(host, init) {
+            Ok(root) => root.declarative = true,
+            Err(err) => {
+                tracing::debug!("Declarative shadow root not attached: {}", err);
+                return false;
+            }
+        }
+        if let Some(contents) = template_contents.borrow().as_ref() {
+            for child in contents.children.borrow().iter() {
+                if let Some(id) = self.convert_node(child, tree) {
+                    tree.append_shadow_child(host, id);
+                }
+            }
+        }
+        true
+    }
 }
 
 impl Default for HtmlParser {
@@ -162,4 +208,44 @@ mod tests {
         // Even fragments get wrapped in html/head/body by html5ever
         assert!(doc.tree().len() > 1);
     }
+    
+    fn element_by_id(tree: &DomTree, id: &str) -> NodeId {
+        let index = tree.nodes.iter()
+            .position(|n| n.as_element().and_then(|e| e.id).is_some_and(|i| tree.resolve(i) == id))
+            .unwrap();
+        NodeId(index as u32)
+    }
+    
+    #[test]
+    fn test_declarative_shadow_root() {
+        let html = "<div id=host><template shadowrootmode=closed shadowrootserializable>\
+            <slot></slot></template><p>Light</p></div>";
+        let doc = HtmlParser::new().parse(html);
+        let tree = doc.tree();
+        let host = element_by_id(tree, "host");
+        
+        let root = tree.shadow_root(host).unwrap();
+        assert_eq!(root.mode, ShadowRootMode::Closed);
+        assert!(root.declarative && root.serializable && !root.delegates_focus);
+        assert_eq!(root.children().len(), 1);
+        
+        // The template itself is not inserted; only the light child remains
+        let children: Vec<_> = tree.children(host).collect();
+        assert_eq!(children.len(), 1);
+    }
+    
+    #[test]
+    fn test_declarative_shadow_fallbacks() {
+        // Second template, invalid mode and invalid host stay plain templates
+        let html = "<div id=a><template shadowrootmode=open></template><template shadowrootmode=open></template></div>\
+            <div id=b><template shadowrootmode=bogus></template></div>\
+            <ul id=c><template shadowrootmode=open></template></ul>";
+        let doc = HtmlParser::new().parse(html);
+        let tree = doc.tree();
+        for (id, templates) in [("a", 1), ("b", 1), ("c", 1)] {
+            let el = element_by_id(tree, id);
+            assert_eq!(tree.shadow_root(el).is_some(), id == "a");
+            assert_eq!(tree.children(el).count(), templates);
+        }
+    }
 }
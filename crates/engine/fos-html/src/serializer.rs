@@ -7,8 +7,9 @@
 //! - Proper HTML escaping
 //! - Void element handling
 //! - Fragment parsing for innerHTML assignment
+//! - getHTML() with serializable shadow roots
 
-use fos_dom::{NodeId, NodeData, DomTree};
+use fos_dom::{NodeId, NodeData, DomTree, ShadowRoot};
 
 /// HTML serializer
 pub struct HtmlSerializer {
@@ -16,6 +17,23 @@ pub struct HtmlSerializer {
     pub pretty_print: bool,
     /// Indentation string
     pub indent: String,
+    /// Shadow roots to include as declarative templates
+    pub shadow_options: GetHtmlOptions,
+}
+
+/// Options for `getHTML()`
+#[derive(Debug, Clone, Default)]
+pub struct GetHtmlOptions {
+    /// Include shadow roots created as serializable
+    pub serializable_shadow_roots: bool,
+    /// Hosts whose shadow roots are included regardless
+    pub shadow_roots: Vec<NodeId>,
+}
+
+impl GetHtmlOptions {
+    fn includes(&self, host: NodeId, root: &ShadowRoot) -> bool {
+        (self.serializable_shadow_roots && root.serializable) || self.shadow_roots.contains(&host)
+    }
 }
 
 /// Void elements (self-closing, no end tag)
@@ -32,6 +50,7 @@ impl Default for HtmlSerializer {
         Self {
             pretty_print: false,
             indent: "  ".to_string(),
+            shadow_options: GetHtmlOptions::default(),
         }
     }
 }
@@ -45,6 +64,7 @@ impl HtmlSerializer {
         Self {
             pretty_print: true,
             indent: "  ".to_string(),
+            shadow_options: GetHtmlOptions::default(),
         }
     }
 
@@ -152,6 +172,20 @@ impl HtmlSerializer {
     }
 
     fn serialize_children(&self, tree: &DomTree, parent_id: NodeId, output: &mut String, depth: usize) {
+        // An included shadow root comes first, as a declarative template
+        if let Some(root) = tree.shadow_root(parent_id).filter(|r| self.shadow_options.includes(parent_id, r)) {
+            if self.pretty_print && depth > 0 {
+                output.push('\n');
+                for _ in 0..depth {
+                    output.push_str(&self.indent);
+                }
+            }
+            output.push_str(&root.declarative_start_tag());
+            for &child_id in root.children() {
+                self.serialize_node(tree, child_id, output, depth + 1);
+            }
+            output.push_str("</template>");
+        }
         for (child_id, _) in tree.children(parent_id) {
             self.serialize_node(tree, child_id, output, depth);
         }
@@ -220,6 +254,12 @@ pub fn get_inner_html(tree: &DomTree, node_id: NodeId) -> String {
     HtmlSerializer::new().serialize_inner(tree, node_id)
 }
 
+/// Utility: `getHTML()` of an element, optionally including shadow roots
+pub fn get_html(tree: &DomTree, node_id: NodeId, options: GetHtmlOptions) -> String {
+    let serializer = HtmlSerializer { shadow_options: options, ..HtmlSerializer::new() };
+    serializer.serialize_inner(tree, node_id)
+}
+
 /// Utility: Get outerHTML of an element
 pub fn get_outer_html(tree: &DomTree, node_id: NodeId) -> String {
     HtmlSerializer::new().serialize_outer(tree, node_id)
@@ -251,6 +291,33 @@ mod tests {
         assert!(!VOID_ELEMENTS.contains(&"div"));
     }
 
+    #[test]
+    fn test_get_html_shadow_roots() {
+        let mut tree = DomTree::new();
+        let host = tree.create_element("div");
+        let slot = tree.create_element("slot");
+        let light = tree.create_text("light");
+        tree.append_child(tree.root(), host);
+        tree.append_child(host, light);
+        let init = fos_dom::ShadowRootInit { serializable: true, ..Default::default() };
+        tree.attach_shadow(host, init).unwrap();
+        tree.append_shadow_child(host, slot);
+
+        // innerHTML never includes shadow roots
+        assert_eq!(get_inner_html(&tree, host), "light");
+        let options = GetHtmlOptions { serializable_shadow_roots: true, ..Default::default() };
+        assert_eq!(
+            get_html(&tree, host, options),
+            "<template shadowrootmode=\"open\" shadowrootserializable=\"\"><slot></slot></template>light"
+        );
+
+        // Non-serializable roots only when asked for explicitly
+        tree.shadow_root_mut(host).unwrap().serializable = false;
+        assert_eq!(get_html(&tree, tree.root(), GetHtmlOptions { serializable_shadow_roots: true, ..Default::default() }), "<div>light</div>");
+        let options = GetHtmlOptions { shadow_roots: vec![host], ..Default::default() };
+        assert_eq!(get_html(&tree, tree.root(), options), "<div><template shadowrootmode=\"open\"><slot></slot></template>light</div>");
+    }
+
     #[test]
     fn test_serializer_creation() {
         let serializer = HtmlSerializer::new();
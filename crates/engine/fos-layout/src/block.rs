@@ -3,7 +3,7 @@
 //! Implements block formatting context (BFC) layout algorithm.
 //! Block boxes stack vertically and expand to fill their container's width.
 
-use crate::{LayoutTree, LayoutBoxId, BoxType, BoxDimensions, EdgeSizes};
+use crate::{LayoutTree, LayoutBoxId, BoxType, BoxDimensions, EdgeSizes, InlineFormattingContext};
 use crate::box_model::Rect;
 
 /// Block formatting context
@@ -14,6 +14,8 @@ pub struct BlockFormattingContext {
     cursor_y: f32,
     /// Previous bottom margin (for margin collapsing)
     prev_margin_bottom: f32,
+    /// First child's top margin already collapsed into the parent's
+    margin_absorbed: bool,
 }
 
 impl BlockFormattingContext {
@@ -23,6 +25,7 @@ impl BlockFormattingContext {
             container_width,
             cursor_y: start_y,
             prev_margin_bottom: 0.0,
+            margin_absorbed: false,
         }
     }
     
@@ -34,6 +37,11 @@ impl BlockFormattingContext {
         containing_width: f32,
         containing_x: f32,
     ) {
+        // A first child's top margin collapses through a parent that doesn't
+        // separate them with border, padding or a new formatting context
+        let collapsed_top = collapsed_margin_top(tree, box_id);
+        let separated = separates_first_child(tree, box_id);
+        
         let layout_box = match tree.get_mut(box_id) {
             Some(b) => b,
             None => return,
@@ -51,7 +59,7 @@ impl BlockFormattingContext {
         dims.content.x = containing_x + dims.margin.left + dims.border.left + dims.padding.left;
         
         // Handle margin collapsing
-        let margin_top = self.collapse_margins(dims.margin.top);
+        let margin_top = self.collapse_margins(collapsed_top);
         
         // Calculate Y position
         dims.content.y = self.cursor_y + margin_top + dims.border.top + dims.padding.top;
@@ -65,15 +73,9 @@ impl BlockFormattingContext {
         // Layout children in a nested BFC
         if let Some(first) = first_child {
             // Child container is the content box (already accounts for padding)
-            let child_container_width = content_width;
-            
-            let mut child_bfc = BlockFormattingContext::new(child_container_width, content_y);
-            
-            let mut child_id = Some(first);
-            while let Some(id) = child_id {
-                child_bfc.layout_block(tree, id, child_container_width, content_x);
-                child_id = tree.get(id).and_then(|b| b.next_sibling);
-            }
+            let mut child_bfc = BlockFormattingContext::new(content_width, content_y);
+            child_bfc.margin_absorbed = !separated;
+            child_bfc.layout_children(tree, first, content_x);
             
             // Set content height based on children
             if let Some(b) = tree.get_mut(box_id) {
@@ -88,8 +90,68 @@ impl BlockFormattingContext {
         self.prev_margin_bottom = dims.margin.bottom;
     }
     
+    /// Layout a run of sibling boxes starting at `first`; consecutive
+    /// inline-level boxes share line boxes, as if wrapped in an anonymous block
+    pub fn layout_children(&mut self, tree: &mut LayoutTree, first: LayoutBoxId, containing_x: f32) {
+        let mut inline_run = Vec::new();
+        let mut child_id = Some(first);
+        while let Some(id) = child_id {
+            if tree.get(id).is_some_and(|b| b.box_type.is_inline_level()) {
+                inline_run.push(id);
+            } else {
+                self.layout_inline_run(tree, &inline_run, containing_x);
+                inline_run.clear();
+                self.layout_block(tree, id, self.container_width, containing_x);
+            }
+            child_id = tree.get(id).and_then(|b| b.next_sibling);
+        }
+        self.layout_inline_run(tree, &inline_run, containing_x);
+    }
+    
+    /// Place inline-level boxes on line boxes below the cursor
+    fn layout_inline_run(&mut self, tree: &mut LayoutTree, run: &[LayoutBoxId], containing_x: f32) {
+        if run.is_empty() {
+            return;
+        }
+        // Line boxes separate the margins around them
+        self.margin_absorbed = false;
+        self.cursor_y += self.prev_margin_bottom;
+        self.prev_margin_bottom = 0.0;
+        
+        let mut ifc = InlineFormattingContext::new(self.container_width, containing_x, self.cursor_y, 0.0);
+        for &id in run {
+            // Shrink-to-fit width, then lay out the contents to find the height
+            let edges = tree.get(id).map(|b| {
+                let d = &b.dimensions;
+                d.margin.horizontal() + d.padding.horizontal() + d.border.horizontal()
+            }).unwrap_or(0.0);
+            let available = (self.container_width - edges).max(0.0);
+            let width = max_content_width(tree, id).min(available);
+            layout_atomic(tree, id, width, 0.0, 0.0);
+            let height = tree.get(id).map(|b| b.dimensions.content.height).unwrap_or(0.0);
+            ifc.add_inline_box(tree, id, width, height);
+        }
+        
+        let lines = ifc.finish();
+        for line in &lines {
+            for fragment in &line.fragments {
+                let width = tree.get(fragment.box_id).map(|b| b.dimensions.content.width).unwrap_or(0.0);
+                layout_atomic(tree, fragment.box_id, width, fragment.x, line.y);
+            }
+        }
+        if let Some(last) = lines.last() {
+            self.cursor_y = last.y + last.height;
+        }
+    }
+    
     /// Collapse adjacent vertical margins
     fn collapse_margins(&mut self, margin_top: f32) -> f32 {
+        if self.margin_absorbed {
+            // Already part of the parent's collapsed top margin
+            self.margin_absorbed = false;
+            self.prev_margin_bottom = 0.0;
+            return 0.0;
+        }
         // Adjacent margins collapse to the larger of the two
         let collapsed = margin_top.max(self.prev_margin_bottom);
         self.prev_margin_bottom = 0.0; // Reset after collapsing
@@ -102,6 +164,69 @@ impl BlockFormattingContext {
     }
 }
 
+/// Whether a box keeps its first child's top margin from collapsing with its own
+fn separates_first_child(tree: &LayoutTree, box_id: LayoutBoxId) -> bool {
+    tree.get(box_id).map_or(true, |b| {
+        b.box_type.establishes_bfc() || b.dimensions.border.top > 0.0 || b.dimensions.padding.top > 0.0
+    })
+}
+
+/// Top margin of a block after collapsing with its first in-flow child's
+fn collapsed_margin_top(tree: &LayoutTree, box_id: LayoutBoxId) -> f32 {
+    let Some(b) = tree.get(box_id) else { return 0.0 };
+    let own = b.dimensions.margin.top;
+    match b.first_child {
+        Some(first) if !separates_first_child(tree, box_id)
+            && tree.get(first).is_some_and(|c| c.box_type.is_block_level()) => {
+            own.max(collapsed_margin_top(tree, first))
+        }
+        _ => own,
+    }
+}
+
+/// Max-content width of a box's content area, from explicit widths of its
+/// descendants (used for shrink-to-fit sizing of atomic inlines)
+fn max_content_width(tree: &LayoutTree, box_id: LayoutBoxId) -> f32 {
+    let Some(b) = tree.get(box_id) else { return 0.0 };
+    if b.dimensions.content.width > 0.0 {
+        return b.dimensions.content.width;
+    }
+    let row = matches!(b.box_type, BoxType::Flex | BoxType::InlineFlex);
+    let mut width: f32 = 0.0;
+    let mut line: f32 = 0.0;
+    for (child_id, child) in tree.children(box_id) {
+        let d = &child.dimensions;
+        let outer = max_content_width(tree, child_id)
+            + d.margin.horizontal() + d.padding.horizontal() + d.border.horizontal();
+        if row || child.box_type.is_inline_level() {
+            line += outer;
+        } else {
+            width = width.max(line).max(outer);
+            line = 0.0;
+        }
+    }
+    width.max(line)
+}
+
+/// Lay out an atomic box at a fixed content width with its margin box at
+/// `(x, y)`; its contents get their own block formatting context
+fn layout_atomic(tree: &mut LayoutTree, box_id: LayoutBoxId, width: f32, x: f32, y: f32) {
+    let Some(b) = tree.get_mut(box_id) else { return };
+    let dims = &mut b.dimensions;
+    dims.content.width = width;
+    dims.content.x = x + dims.margin.left + dims.border.left + dims.padding.left;
+    dims.content.y = y + dims.margin.top + dims.border.top + dims.padding.top;
+    let (content_x, content_y) = (dims.content.x, dims.content.y);
+    
+    if let Some(first) = b.first_child {
+        let mut bfc = BlockFormattingContext::new(width, content_y);
+        bfc.layout_children(tree, first, content_x);
+        if let Some(b) = tree.get_mut(box_id) {
+            b.dimensions.content.height = (bfc.cursor_y - content_y).max(0.0);
+        }
+    }
+}
+
 /// Apply block layout to a tree starting from root
 pub fn layout_block_tree(tree: &mut LayoutTree, viewport_width: f32, viewport_height: f32) {
     let root = match tree.root() {
@@ -120,12 +245,7 @@ pub fn layout_block_tree(tree: &mut LayoutTree, viewport_width: f32, viewport_he
     let first_child = tree.get(root).and_then(|b| b.first_child);
     if let Some(first) = first_child {
         let mut bfc = BlockFormattingContext::new(viewport_width, 0.0);
-        
-        let mut child_id = Some(first);
-        while let Some(id) = child_id {
-            bfc.layout_block(tree, id, viewport_width, 0.0);
-            child_id = tree.get(id).and_then(|b| b.next_sibling);
-        }
+        bfc.layout_children(tree, first, 0.0);
         
         // Set root height
         if let Some(root_box) = tree.get_mut(root) {
@@ -240,4 +360,59 @@ mod tests {
         assert_eq!(c.dimensions.content.x, 20.0); // Offset by parent padding
         assert_eq!(c.dimensions.content.y, 20.0);
     }
+    
+    #[test]
+    fn test_flow_root_contains_child_margins() {
+        for (box_type, expected_y) in [(BoxType::Block, 40.0), (BoxType::FlowRoot, 0.0)] {
+            let mut tree = LayoutTree::new();
+            let root = tree.create_box(BoxType::Block, None);
+            tree.set_root(root);
+            let parent = tree.create_box(box_type, None);
+            let child = tree.create_box(BoxType::Block, None);
+            tree.append_child(root, parent);
+            tree.append_child(parent, child);
+            if let Some(b) = tree.get_mut(child) {
+                b.dimensions.margin.top = 40.0;
+                b.dimensions.content.height = 10.0;
+            }
+            
+            layout_block_tree(&mut tree, 800.0, 600.0);
+            
+            // A plain block's first child margin collapses through it;
+            // a flow root keeps it inside
+            assert_eq!(tree.get(parent).unwrap().dimensions.content.y, expected_y);
+            assert_eq!(tree.get(child).unwrap().dimensions.content.y, 40.0);
+        }
+    }
+    
+    #[test]
+    fn test_inline_containers_share_lines() {
+        let mut tree = LayoutTree::new();
+        let root = tree.create_box(BoxType::Block, None);
+        tree.set_root(root);
+        let flex = tree.create_box(BoxType::InlineFlex, None);
+        let grid = tree.create_box(BoxType::InlineGrid, None);
+        let block = tree.create_box(BoxType::Block, None);
+        for id in [flex, grid, block] {
+            tree.append_child(root, id);
+        }
+        let item = tree.create_box(BoxType::Block, None);
+        tree.append_child(flex, item);
+        if let Some(b) = tree.get_mut(item) {
+            b.dimensions.content = Rect::new(0.0, 0.0, 120.0, 30.0);
+        }
+        if let Some(b) = tree.get_mut(grid) {
+            b.dimensions.content = Rect::new(0.0, 0.0, 200.0, 20.0);
+        }
+        
+        layout_block_tree(&mut tree, 800.0, 600.0);
+        
+        let flex = tree.get(flex).unwrap().dimensions.content;
+        let grid = tree.get(grid).unwrap().dimensions.content;
+        // Shrink-to-fit, side by side on one line
+        assert_eq!((flex.x, flex.y, flex.width, flex.height), (0.0, 0.0, 120.0, 30.0));
+        assert_eq!((grid.x, grid.y), (120.0, 0.0));
+        // The following block starts below the line box
+        assert_eq!(tree.get(block).unwrap().dimensions.content.y, 30.0);
+    }
 }
@@ -182,12 +182,18 @@ pub enum BoxType {
     Inline,
     /// Inline-block box
     InlineBlock,
+    /// Block box establishing a new block formatting context
+    FlowRoot,
     /// Flex container
     Flex,
+    /// Inline-level flex container
+    InlineFlex,
     /// Flex item
     FlexItem,
     /// Grid container (future)
     Grid,
+    /// Inline-level grid container
+    InlineGrid,
     /// Anonymous block (for text in block context)
     AnonymousBlock,
     /// Anonymous inline (for blocks in inline context)
@@ -199,12 +205,26 @@ pub enum BoxType {
 impl BoxType {
     /// Is this a block-level box?
     pub fn is_block_level(&self) -> bool {
-        matches!(self, BoxType::Block | BoxType::Flex | BoxType::Grid | BoxType::AnonymousBlock)
+        matches!(self, BoxType::Block | BoxType::FlowRoot | BoxType::Flex | BoxType::Grid | BoxType::AnonymousBlock)
     }
     
     /// Is this an inline-level box?
     pub fn is_inline_level(&self) -> bool {
-        matches!(self, BoxType::Inline | BoxType::InlineBlock | BoxType::Text | BoxType::AnonymousInline)
+        matches!(
+            self,
+            BoxType::Inline | BoxType::InlineBlock | BoxType::InlineFlex | BoxType::InlineGrid
+                | BoxType::Text | BoxType::AnonymousInline
+        )
+    }
+    
+    /// Is this an atomic inline (laid out as a single unbreakable box on a line)?
+    pub fn is_atomic_inline(&self) -> bool {
+        matches!(self, BoxType::InlineBlock | BoxType::InlineFlex | BoxType::InlineGrid)
+    }
+    
+    /// Does this box establish a new block formatting context for its children?
+    pub fn establishes_bfc(&self) -> bool {
+        self.is_atomic_inline() || matches!(self, BoxType::FlowRoot | BoxType::Flex | BoxType::Grid)
     }
 }
 
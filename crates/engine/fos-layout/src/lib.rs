@@ -100,6 +100,7 @@ pub use math_layout::{
 
 use fos_dom::{DomTree, NodeId, Document};
 use fos_css::computed::{ComputedStyle, Display};
+use fos_css::Viewport;
 
 /// Layout a document and return the layout tree
 pub fn layout_document(
@@ -108,6 +109,19 @@ pub fn layout_document(
     viewport_width: f32,
     viewport_height: f32,
 ) -> LayoutTree {
+    layout_document_in(document, styles, &Viewport::new(viewport_width, viewport_height))
+}
+
+/// Layout a document into a viewport, resolving viewport units against it
+///
+/// The root box takes the dynamic viewport size, so showing or hiding
+/// retractable browser UI resizes the initial containing block.
+pub fn layout_document_in(
+    document: &Document,
+    styles: &std::collections::HashMap<NodeId, ComputedStyle>,
+    viewport: &Viewport,
+) -> LayoutTree {
+    let (viewport_width, viewport_height) = viewport.dynamic();
     let mut tree = LayoutTree::new();
     
     // Create root layout box for viewport
@@ -124,7 +138,7 @@ pub fn layout_document(
     let body = document.body();
     
     if body.is_valid() {
-        build_layout_tree(&mut tree, dom, styles, body, root);
+        build_layout_tree(&mut tree, dom, styles, viewport, body, root);
     }
     
     // Perform layout
@@ -140,6 +154,7 @@ fn build_layout_tree(
     layout_tree: &mut LayoutTree,
     dom: &DomTree,
     styles: &std::collections::HashMap<NodeId, ComputedStyle>,
+    viewport: &Viewport,
     node_id: NodeId,
     parent_layout_id: LayoutBoxId,
 ) {
@@ -154,11 +169,21 @@ fn build_layout_tree(
     // Determine box type from display
     let box_type = match style.map(|s| s.display) {
         Some(Display::None) => return, // Don't create box for display:none
+        Some(Display::Contents) => {
+            // No box of its own: children are boxed as if they were ours
+            for (child_id, _) in dom.children(node_id) {
+                build_layout_tree(layout_tree, dom, styles, viewport, child_id, parent_layout_id);
+            }
+            return;
+        }
         Some(Display::Flex) => BoxType::Flex,
+        Some(Display::InlineFlex) => BoxType::InlineFlex,
         Some(Display::Grid) => BoxType::Grid,
+        Some(Display::InlineGrid) => BoxType::InlineGrid,
         Some(Display::Inline) => BoxType::Inline,
         Some(Display::InlineBlock) => BoxType::InlineBlock,
-        Some(Display::Block) | Some(Display::Contents) | None => BoxType::Block,
+        Some(Display::FlowRoot) => BoxType::FlowRoot,
+        Some(Display::Block) | None => BoxType::Block,
     };
     
     // Create layout box
@@ -167,59 +192,62 @@ fn build_layout_tree(
     
     // Apply style to dimensions
     if let (Some(layout_box), Some(s)) = (layout_tree.get_mut(layout_id), style) {
-        apply_style_to_box(layout_box, s);
+        apply_style_to_box(layout_box, s, viewport);
     }
     
     // Process children
     for (child_id, _) in dom.children(node_id) {
-        build_layout_tree(layout_tree, dom, styles, child_id, layout_id);
+        build_layout_tree(layout_tree, dom, styles, viewport, child_id, layout_id);
     }
 }
 
 /// Apply computed style to layout box dimensions
-fn apply_style_to_box(layout_box: &mut LayoutBox, style: &ComputedStyle) {
+fn apply_style_to_box(layout_box: &mut LayoutBox, style: &ComputedStyle, viewport: &Viewport) {
     use fos_css::computed::SizeValue;
     
     // Apply margins
     layout_box.dimensions.margin = EdgeSizes {
-        top: size_to_px(&style.margin.top),
-        right: size_to_px(&style.margin.right),
-        bottom: size_to_px(&style.margin.bottom),
-        left: size_to_px(&style.margin.left),
+        top: size_to_px(&style.margin.top, viewport),
+        right: size_to_px(&style.margin.right, viewport),
+        bottom: size_to_px(&style.margin.bottom, viewport),
+        left: size_to_px(&style.margin.left, viewport),
     };
     
     // Apply padding
     layout_box.dimensions.padding = EdgeSizes {
-        top: size_to_px(&style.padding.top),
-        right: size_to_px(&style.padding.right),
-        bottom: size_to_px(&style.padding.bottom),
-        left: size_to_px(&style.padding.left),
+        top: size_to_px(&style.padding.top, viewport),
+        right: size_to_px(&style.padding.right, viewport),
+        bottom: size_to_px(&style.padding.bottom, viewport),
+        left: size_to_px(&style.padding.left, viewport),
     };
     
     // Apply border widths
     layout_box.dimensions.border = EdgeSizes {
-        top: size_to_px(&style.border_width.top),
-        right: size_to_px(&style.border_width.right),
-        bottom: size_to_px(&style.border_width.bottom),
-        left: size_to_px(&style.border_width.left),
+        top: size_to_px(&style.border_width.top, viewport),
+        right: size_to_px(&style.border_width.right, viewport),
+        bottom: size_to_px(&style.border_width.bottom, viewport),
+        left: size_to_px(&style.border_width.left, viewport),
     };
     
     // Apply explicit dimensions
-    if let SizeValue::Length(v, _) = &style.width {
-        layout_box.dimensions.content.width = *v;
+    if let SizeValue::Length(v, unit) = &style.width {
+        layout_box.dimensions.content.width = viewport.resolve(*v, *unit).unwrap_or(*v);
     }
-    if let SizeValue::Length(v, _) = &style.height {
-        layout_box.dimensions.content.height = *v;
+    if let SizeValue::Length(v, unit) = &style.height {
+        layout_box.dimensions.content.height = viewport.resolve(*v, *unit).unwrap_or(*v);
     }
 }
 
 /// Convert SizeValue to pixels (simplified)
-fn size_to_px(size: &fos_css::computed::SizeValue) -> f32 {
+fn size_to_px(size: &fos_css::computed::SizeValue, viewport: &Viewport) -> f32 {
     use fos_css::computed::SizeValue;
     use fos_css::properties::LengthUnit;
     
     match size {
         SizeValue::Length(v, unit) => {
+            if let Some(px) = viewport.resolve(*v, *unit) {
+                return px;
+            }
             match unit {
                 LengthUnit::Px => *v,
                 LengthUnit::Em => v * 16.0, // Assuming 16px font
@@ -255,4 +283,61 @@ mod tests {
         assert_eq!(c.dimensions.content.width, 800.0);
         assert_eq!(c.dimensions.content.y, 0.0);
     }
+    
+    #[test]
+    fn test_display_contents_and_inline_containers() {
+        let mut document = Document::new("about:blank");
+        let body = document.body();
+        let tree = document.tree_mut();
+        let wrapper = tree.create_element("div");
+        let child = tree.create_element("div");
+        let chip = tree.create_element("span");
+        tree.append_child(body, wrapper);
+        tree.append_child(wrapper, child);
+        tree.append_child(body, chip);
+        
+        let mut styles = std::collections::HashMap::new();
+        styles.insert(wrapper, ComputedStyle { display: Display::Contents, ..Default::default() });
+        styles.insert(child, ComputedStyle::default());
+        styles.insert(chip, ComputedStyle { display: Display::InlineFlex, ..Default::default() });
+        
+        let tree = layout_document(&document, &styles, 800.0, 600.0);
+        let body_box = tree.children(tree.root().unwrap()).next().unwrap().0;
+        let boxes: Vec<_> = tree.children(body_box).map(|(_, b)| (b.dom_node, b.box_type)).collect();
+        // The wrapper has no box; its child is hoisted into the body's box
+        assert_eq!(boxes, vec![(Some(child), BoxType::Block), (Some(chip), BoxType::InlineFlex)]);
+    }
+    
+    #[test]
+    fn test_viewport_units() {
+        use fos_css::computed::SizeValue;
+        use fos_css::properties::LengthUnit;
+        
+        let mut document = Document::new("about:blank");
+        let body = document.body();
+        let tree = document.tree_mut();
+        let panel = tree.create_element("div");
+        tree.append_child(body, panel);
+        
+        let mut styles = std::collections::HashMap::new();
+        styles.insert(panel, ComputedStyle {
+            height: SizeValue::Length(50.0, LengthUnit::Dvh),
+            ..Default::default()
+        });
+        
+        let mut viewport = Viewport::new(400.0, 800.0).with_chrome(100.0);
+        let panel_height = |viewport: &Viewport| {
+            let tree = layout_document_in(&document, &styles, viewport);
+            let body_box = tree.children(tree.root().unwrap()).next().unwrap().0;
+            let (_, panel_box) = tree.children(body_box).next().unwrap();
+            panel_box.dimensions.content.height
+        };
+        assert_eq!(panel_height(&viewport), 350.0);
+        
+        // Hiding the toolbar relayouts dynamic units; pinch zoom does not
+        assert!(viewport.set_chrome_visible(false).needs_relayout());
+        assert_eq!(panel_height(&viewport), 400.0);
+        assert!(!viewport.set_pinch_zoom(3.0).needs_relayout());
+        assert_eq!(panel_height(&viewport), 400.0);
+    }
 }
@@ -6,6 +6,8 @@
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
+use fos_dom::url::PublicSuffixList;
+
 // ============================================================================
 // Cache Result
 // ============================================================================
@@ -91,20 +93,13 @@ impl CachePartitionKey {
         format!("{}|{}|{}", self.top_level_site, self.is_cross_site, url)
     }
     
-    /// Extract eTLD+1 from a URL (simplified)
+    /// Extract eTLD+1 from a URL
     pub fn extract_site(url: &str) -> Option<String> {
-        // Simple extraction: find host portion
         let url = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://"))?;
         let host = url.split('/').next()?;
         let host = host.split(':').next()?; // Remove port
         
-        // Simplified eTLD+1: just take domain + TLD
-        let parts: Vec<&str> = host.split('.').collect();
-        if parts.len() >= 2 {
-            Some(format!("{}.{}", parts[parts.len() - 2], parts[parts.len() - 1]))
-        } else {
-            Some(host.to_string())
-        }
+        Some(PublicSuffixList::current().registrable_domain(host).unwrap_or_else(|| host.to_string()))
     }
 }
 
@@ -254,6 +249,22 @@ impl HttpCache {
         }
     }
     
+    /// Drop the entry for `url`, e.g. when another tab saw it change
+    pub fn remove(&mut self, url: &str) -> bool {
+        match self.entries.remove(url) {
+            Some(entry) => {
+                self.current_size = self.current_size.saturating_sub(entry.body.len());
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Every entry with its URL, fresh or not, in no particular order
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &CacheEntry)> {
+        self.entries.iter().map(|(url, entry)| (url.as_str(), entry))
+    }
+    
     /// Check if URL is cached (without updating access time)
     pub fn contains(&self, url: &str) -> bool {
         self.entries.get(url).map(|e| e.is_fresh()).unwrap_or(false)
@@ -490,6 +501,10 @@ mod tests {
         let stats = cache.stats();
         assert_eq!(stats.entry_count, 2);
         assert_eq!(stats.total_size, 10);
+        
+        let mut urls: Vec<_> = cache.entries().map(|(url, _)| url).collect();
+        urls.sort();
+        assert_eq!(urls, ["url1", "url2"]);
     }
     
     #[test]
@@ -6,14 +6,14 @@
 use std::io::{self, BufReader, Read, Write};
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
-use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 
 use crate::tcp::{TcpConnection, TcpConfig};
-use crate::tls::{TlsStream, TlsConfig, TlsState};
+use crate::tls::{TlsStream, TlsConfig, TlsState, TlsError};
 use crate::http1::{Http1Request, Http1Response, Http1Parser, HttpVersion};
 use crate::http2::{Http2Connection, Frame, Http2Event, Http2Error};
 use crate::cookies::{CookieJar, Cookie};
+use crate::client_cert::ClientCertManager;
 use crate::connection_pool::{ConnectionPool, HostKey, PoolConfig, AcquireResult, ConnId};
 use crate::quic::{
     QuicConnection, ConnectionState, QuicStream,
@@ -22,8 +22,21 @@ use crate::quic::{
     Http3Frame, Http3Setting,
     PushManager, PushState,
 };
+use crate::timing::{RequestTimings, TimedReader, elapsed_ms};
+use crate::upload::Upload;
+use crate::deadline::{Deadline, resolve_within};
+use crate::http_auth::{AuthCache, AuthPrompt, AuthScheme, AuthTarget, Challenge, CredentialProvider};
+#[cfg(feature = "integrated-auth")]
+use crate::negotiate::{self, IntegratedAuth, IntegratedScheme, MAX_HANDSHAKE_LEGS};
 use crate::{Response, NetError};
 
+/// Challenges answered for one request before giving up
+const MAX_AUTH_ATTEMPTS: u32 = 3;
+
+/// Identifies each client's login to the connection pool
+#[cfg(feature = "integrated-auth")]
+static NEXT_AUTH_IDENTITY: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+
 /// HTTP client configuration
 #[derive(Debug, Clone)]
 pub struct ClientConfig {
@@ -47,6 +60,14 @@ pub struct ClientConfig {
     pub prefer_http3: bool,
     /// HTTP/3 idle timeout
     pub http3_idle_timeout: Duration,
+    /// User asked to reduce data usage; sends `Save-Data: on`
+    pub save_data: bool,
+    /// Hosts connected to despite certificate errors
+    pub certificate_overrides: Vec<String>,
+    /// HTTP proxy as `host:port`; HTTPS goes through a `CONNECT` tunnel
+    pub proxy: Option<String>,
+    /// Certificates for servers asking for one
+    pub client_certs: Option<Arc<ClientCertManager>>,
 }
 
 impl Default for ClientConfig {
@@ -62,6 +83,10 @@ impl Default for ClientConfig {
             http3_enabled: true,
             prefer_http3: false,
             http3_idle_timeout: Duration::from_secs(30),
+            save_data: false,
+            certificate_overrides: Vec::new(),
+            proxy: None,
+            client_certs: None,
         }
     }
 }
@@ -69,12 +94,20 @@ impl Default for ClientConfig {
 /// HTTP client builder
 pub struct HttpClientBuilder {
     config: ClientConfig,
+    auth_cache: AuthCache,
+    credential_provider: Option<Arc<dyn CredentialProvider>>,
+    #[cfg(feature = "integrated-auth")]
+    integrated_auth: Option<IntegratedAuth>,
 }
 
 impl HttpClientBuilder {
     pub fn new() -> Self {
         Self {
             config: ClientConfig::default(),
+            auth_cache: AuthCache::new(),
+            credential_provider: None,
+            #[cfg(feature = "integrated-auth")]
+            integrated_auth: None,
         }
     }
     
@@ -120,8 +153,61 @@ impl HttpClientBuilder {
         self
     }
     
+    /// Ask servers for lighter responses with `Save-Data: on`
+    pub fn save_data(mut self, enabled: bool) -> Self {
+        self.config.save_data = enabled;
+        self
+    }
+    
+    /// Connect to `host` even if its certificate fails validation
+    pub fn allow_invalid_certificate(mut self, host: &str) -> Self {
+        self.config.certificate_overrides.push(host.to_ascii_lowercase());
+        self
+    }
+    
+    /// Answer servers asking for a client certificate from `manager`
+    pub fn client_certificates(mut self, manager: Arc<ClientCertManager>) -> Self {
+        self.config.client_certs = Some(manager);
+        self
+    }
+    
+    /// Send requests through an HTTP proxy, given as `host:port` or
+    /// `http://host:port`
+    pub fn proxy(mut self, proxy: &str) -> Self {
+        let proxy = proxy.trim_start_matches("http://").trim_end_matches('/');
+        self.config.proxy = Some(proxy.to_ascii_lowercase());
+        self
+    }
+    
+    /// Share credentials accepted by origins and proxies with other clients
+    pub fn auth_cache(mut self, cache: AuthCache) -> Self {
+        self.auth_cache = cache;
+        self
+    }
+    
+    /// Ask `provider` for credentials when a server or proxy challenges
+    pub fn credential_provider(mut self, provider: Arc<dyn CredentialProvider>) -> Self {
+        self.credential_provider = Some(provider);
+        self
+    }
+    
+    /// Answer Negotiate and NTLM challenges from servers the policy allows
+    /// with the user's login
+    #[cfg(feature = "integrated-auth")]
+    pub fn integrated_auth(mut self, auth: IntegratedAuth) -> Self {
+        self.integrated_auth = Some(auth);
+        self
+    }
+    
     pub fn build(self) -> HttpClient {
-        HttpClient::with_config(self.config)
+        let mut client = HttpClient::with_config(self.config);
+        client.auth_cache = self.auth_cache;
+        client.credential_provider = self.credential_provider;
+        #[cfg(feature = "integrated-auth")]
+        {
+            client.integrated_auth = self.integrated_auth;
+        }
+        client
     }
 }
 
@@ -141,6 +227,24 @@ pub struct HttpClient {
     pool: ConnectionPool,
     /// Alt-Svc cache for HTTP/3 discovery
     alt_svc_cache: AltSvcCache,
+    /// Phase timings of the last exchange
+    last_timings: Option<RequestTimings>,
+    /// Bound on later requests, shared by all their phases
+    deadline: Deadline,
+    /// Credentials sent to origins and the proxy without waiting for a
+    /// challenge
+    auth_cache: AuthCache,
+    /// Prompt for credentials on 401 and 407
+    credential_provider: Option<Arc<dyn CredentialProvider>>,
+    /// Negotiate and NTLM with the user's login
+    #[cfg(feature = "integrated-auth")]
+    integrated_auth: Option<IntegratedAuth>,
+    /// Connections Negotiate or NTLM authenticated, kept open by pool ID
+    #[cfg(feature = "integrated-auth")]
+    authenticated: HashMap<ConnId, HttpConnection>,
+    /// This client's login in the connection pool
+    #[cfg(feature = "integrated-auth")]
+    auth_identity: u64,
 }
 
 impl HttpClient {
@@ -166,9 +270,31 @@ impl HttpClient {
             cookies: CookieJar::new(),
             pool: ConnectionPool::new(pool_config),
             alt_svc_cache: AltSvcCache::new(),
+            last_timings: None,
+            deadline: Deadline::none(),
+            auth_cache: AuthCache::new(),
+            credential_provider: None,
+            #[cfg(feature = "integrated-auth")]
+            integrated_auth: None,
+            #[cfg(feature = "integrated-auth")]
+            authenticated: HashMap::new(),
+            #[cfg(feature = "integrated-auth")]
+            auth_identity: NEXT_AUTH_IDENTITY.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
         }
     }
     
+    /// Turn the data saver preference on or off for later requests
+    pub fn set_save_data(&mut self, enabled: bool) {
+        self.config.save_data = enabled;
+    }
+    
+    /// Bound later requests by `deadline`: DNS, connecting, TLS and the
+    /// exchange each get what is left of it, and cancelling its token
+    /// closes the connection at the next wakeup
+    pub fn set_deadline(&mut self, deadline: Deadline) {
+        self.deadline = deadline;
+    }
+    
     /// Make a GET request
     pub fn get(&mut self, url: &str) -> Result<Response, NetError> {
         self.request("GET", url, None, None)
@@ -187,7 +313,19 @@ impl HttpClient {
         headers: Option<Vec<(String, String)>>,
         body: Option<Vec<u8>>,
     ) -> Result<Response, NetError> {
-        self.request_with_redirects(method, url, headers, body, 0)
+        self.request_with_redirects(method, url, headers, body.map(RequestBody::Bytes), 0, 0)
+    }
+    
+    /// Make an HTTP request whose body is streamed from its source, for
+    /// uploads too large to hold in memory
+    pub fn upload(
+        &mut self,
+        method: &str,
+        url: &str,
+        headers: Option<Vec<(String, String)>>,
+        upload: Upload,
+    ) -> Result<Response, NetError> {
+        self.request_with_redirects(method, url, headers, Some(RequestBody::Stream(upload)), 0, 0)
     }
     
     fn request_with_redirects(
@@ -195,14 +333,22 @@ impl HttpClient {
         method: &str,
         url: &str,
         headers: Option<Vec<(String, String)>>,
-        body: Option<Vec<u8>>,
+        body: Option<RequestBody>,
         redirect_count: u32,
+        auth_attempts: u32,
     ) -> Result<Response, NetError> {
+        // Redirects and retries share the one deadline
+        self.deadline.check()?;
+        
         // Parse URL
         let parsed = UrlParts::parse(url)?;
+        let origin = parsed.origin();
+        // Plain HTTP through a proxy names the whole URL in the request line
+        let proxied_http = self.config.proxy.is_some() && !parsed.is_https;
         
         // Build request
-        let mut req = Http1Request::new(method, &parsed.path_and_query());
+        let target = if proxied_http { parsed.absolute() } else { parsed.path_and_query() };
+        let mut req = Http1Request::new(method, &target);
         
         // Add Host header
         req = req.header("Host", &parsed.host_with_port());
@@ -215,24 +361,53 @@ impl HttpClient {
             req = req.header(name, value);
         }
         
+        // Add data saver hint
+        if self.config.save_data {
+            req = req.header("Save-Data", "on");
+        }
+        
         // Add custom headers
-        if let Some(hdrs) = headers {
-            for (name, value) in hdrs {
-                req = req.header(&name, &value);
-            }
+        for (name, value) in headers.iter().flatten() {
+            req = req.header(name, value);
+        }
+        
+        // Add credentials accepted before, unless the caller sent its own
+        let has_header = |name: &str| headers.iter().flatten().any(|(n, _)| n.eq_ignore_ascii_case(name));
+        let server_auth = if has_header("authorization") {
+            None
+        } else {
+            self.auth_cache.authorization(AuthTarget::Server, &origin, method, &parsed.path_and_query())
+        };
+        if let Some(value) = server_auth {
+            req = req.header("Authorization", &value);
+        }
+        let proxy_auth = self.config.proxy.as_deref()
+            .filter(|_| proxied_http)
+            .and_then(|proxy| self.auth_cache.authorization(AuthTarget::Proxy, proxy, method, &target));
+        if let Some(value) = proxy_auth {
+            req = req.header("Proxy-Authorization", &value);
         }
         
         // Add cookies
         if self.config.cookies_enabled {
-            if let Some(cookie_header) = self.cookies.get_cookie_header(&parsed.host, &parsed.path, parsed.is_https) {
+            if let Some(cookie_header) = self.cookies.cookie_header_for_request(&parsed.host, &parsed.path, parsed.is_https) {
                 req = req.header("Cookie", &cookie_header);
             }
         }
         
-        // Add body
-        if let Some(b) = body.clone() {
-            req = req.body(b);
-        }
+        // Add body; streamed bodies are written after the head
+        let upload = match body.clone() {
+            Some(RequestBody::Bytes(b)) => {
+                req = req.body(b);
+                None
+            }
+            Some(RequestBody::Stream(upload)) => {
+                let (name, value) = upload.framing_header();
+                req = req.header(name, &value);
+                Some(upload)
+            }
+            None => None,
+        };
         
         // Add Connection header
         if self.config.keep_alive {
@@ -241,8 +416,25 @@ impl HttpClient {
             req = req.header("Connection", "close");
         }
         
-        // Make the connection
-        let response = self.execute_request(&parsed, req)?;
+        // Make the connection, reusing one Negotiate or NTLM authenticated;
+        // streamed bodies always go on a connection of their own
+        #[cfg(feature = "integrated-auth")]
+        let response = match upload.is_none().then(|| self.send_authenticated(&parsed, &req)).flatten() {
+            Some(response) => response,
+            None => self.execute_request(&parsed, req.clone(), upload.as_ref())
+                .map_err(|e| self.deadline.error().unwrap_or(e))?,
+        };
+        #[cfg(not(feature = "integrated-auth"))]
+        let response = self.execute_request(&parsed, req, upload.as_ref())
+            .map_err(|e| self.deadline.error().unwrap_or(e))?;
+        
+        // Negotiate and NTLM authenticate a connection of their own; Basic
+        // and Digest remain if that fails
+        #[cfg(feature = "integrated-auth")]
+        let response = match upload {
+            None => self.authenticate_connection(&parsed, &req, &response).unwrap_or(response),
+            Some(_) => response,
+        };
         
         // Store cookies from response
         if self.config.cookies_enabled {
@@ -253,6 +445,17 @@ impl HttpClient {
             }
         }
         
+        // Answer authentication challenges and try again
+        if let Some(auth_target) = AuthTarget::for_status(response.status) {
+            let key = match auth_target {
+                AuthTarget::Server => Some(origin.as_str()),
+                AuthTarget::Proxy => self.config.proxy.as_deref(),
+            };
+            if auth_attempts < MAX_AUTH_ATTEMPTS && key.is_some_and(|key| self.authenticate(auth_target, key, &response)) {
+                return self.request_with_redirects(method, url, headers, body, redirect_count, auth_attempts + 1);
+            }
+        }
+        
         // Handle redirects
         if response.status >= 300 && response.status < 400 && redirect_count < self.config.max_redirects {
             if let Some(location) = response.headers.iter()
@@ -268,19 +471,225 @@ impl HttpClient {
                     ("GET", None)
                 };
                 
-                return self.request_with_redirects(new_method, &new_url, None, new_body, redirect_count + 1);
+                return self.request_with_redirects(new_method, &new_url, None, new_body, redirect_count + 1, 0);
             }
         }
         
         Ok(response)
     }
     
-    fn execute_request(&mut self, url: &UrlParts, req: Http1Request) -> Result<Response, NetError> {
+    /// Get credentials for a challenge in `response`, from the cache when
+    /// only the Digest nonce went stale and from the provider otherwise.
+    /// `false` if the challenge can't be answered.
+    fn authenticate(&self, target: AuthTarget, key: &str, response: &Response) -> bool {
+        let challenges: Vec<Challenge> = response.headers.iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case(target.challenge_header()))
+            .flat_map(|(_, value)| Challenge::parse_all(value))
+            .collect();
+        // Prefer Digest, which doesn't send the password
+        let Some(challenge) = challenges.iter()
+            .find(|c| c.scheme == AuthScheme::Digest)
+            .or(challenges.first())
+            .cloned()
+        else {
+            return false;
+        };
+        
+        if challenge.is_stale() && self.auth_cache.update_challenge(target, key, challenge.clone()) {
+            return true;
+        }
+        
+        // Cached credentials were sent with the request, so they failed
+        let retry = self.auth_cache.credentials(target, key).is_some();
+        self.auth_cache.remove(target, key);
+        let Some(provider) = &self.credential_provider else { return false };
+        let prompt = AuthPrompt {
+            target,
+            origin: key.to_string(),
+            realm: challenge.realm.clone(),
+            scheme: challenge.scheme,
+            retry,
+        };
+        match provider.credentials(&prompt) {
+            Some(credentials) => {
+                self.auth_cache.insert(target, key, challenge, credentials);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Answer a 401 offering Negotiate or NTLM from a server the policy
+    /// allows: run the handshake on a new connection and keep it for later
+    /// requests. The final response, or None if the server can't be
+    /// authenticated this way.
+    #[cfg(feature = "integrated-auth")]
+    fn authenticate_connection(&mut self, url: &UrlParts, req: &Http1Request, response: &Response) -> Option<Response> {
+        // Only direct connections; the proxy would be sent the login
+        if response.status != 401 || self.config.proxy.is_some() {
+            return None;
+        }
+        let auth = self.integrated_auth.clone()?;
+        let offered = response.headers.iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("www-authenticate"))
+            .map(|(_, value)| value.as_str());
+        let (scheme, _) = auth.choose(&url.host, offered)?;
+        
+        let port = url.port.unwrap_or(if url.is_https { 443 } else { 80 });
+        let key = HostKey::new(&url.host, port, url.is_https);
+        let AcquireResult::Created(id) = self.pool.create_authenticated(&key, self.auth_identity) else {
+            return None;
+        };
+        match self.handshake(&auth, scheme, url, port, req) {
+            Ok((response, _)) if response.status == 401 => {
+                tracing::debug!("{} rejected {} credentials", url.host, scheme);
+                self.pool.close(id);
+                None
+            }
+            Ok((response, connection)) => {
+                match connection {
+                    Some(connection) => {
+                        self.authenticated.insert(id, connection);
+                        self.pool.release(id);
+                    }
+                    None => self.pool.close(id),
+                }
+                Some(response)
+            }
+            Err(e) => {
+                tracing::debug!("{} authentication with {} failed: {}", scheme, url.host, e);
+                self.pool.close(id);
+                None
+            }
+        }
+    }
+    
+    /// Exchange tokens with the server over one connection until it stops
+    /// challenging. Returns the final response and the connection, unless
+    /// the server closes it.
+    #[cfg(feature = "integrated-auth")]
+    fn handshake(&mut self, auth: &IntegratedAuth, scheme: IntegratedScheme, url: &UrlParts, port: u16, req: &Http1Request) -> Result<(Response, Option<HttpConnection>), NetError> {
+        let default_port = if url.is_https { 443 } else { 80 };
+        let failed = |e: negotiate::IntegratedAuthError| NetError::Network(e.to_string());
+        let mut context = auth.backend.start(scheme, &auth.policy.service_host(&url.host, port, default_port)).map_err(failed)?;
+        
+        let mut timings = RequestTimings::default();
+        let mut connection = self.connect_http1(url, port, &mut timings)?;
+        let mut token = context.step(None).map_err(failed)?;
+        for _ in 0..MAX_HANDSHAKE_LEGS {
+            let mut leg = req.clone();
+            leg.headers.retain(|(name, _)| !name.eq_ignore_ascii_case("connection") && !name.eq_ignore_ascii_case("authorization"));
+            let leg = leg
+                .header("Authorization", &negotiate::authorization(scheme, &token))
+                .header("Connection", "keep-alive");
+            let response = self.send_and_receive_http1(&mut connection, leg, None, &mut timings)?;
+            
+            let offered = response.headers.iter()
+                .filter(|(name, _)| name.eq_ignore_ascii_case("www-authenticate"))
+                .map(|(_, value)| value.as_str());
+            let server_token = IntegratedScheme::offered(offered).into_iter()
+                .find(|(s, _)| *s == scheme)
+                .and_then(|(_, token)| token);
+            match server_token {
+                Some(server_token) if response.status == 401 => {
+                    token = context.step(Some(&server_token)).map_err(failed)?;
+                }
+                server_token => {
+                    // A token with the final response authenticates the server
+                    if let Some(server_token) = server_token.filter(|_| response.status != 401) {
+                        context.step(Some(&server_token)).map_err(failed)?;
+                    }
+                    self.last_timings = Some(timings);
+                    let keep = !closes_connection(&response);
+                    return Ok((response, keep.then_some(connection)));
+                }
+            }
+        }
+        Err(NetError::Network(format!("{} handshake did not finish", scheme)))
+    }
+    
+    /// Send a request on an idle connection authenticated for its host;
+    /// None if there is none or it was closed in the meantime
+    #[cfg(feature = "integrated-auth")]
+    fn send_authenticated(&mut self, url: &UrlParts, req: &Http1Request) -> Option<Response> {
+        let pool = &self.pool;
+        self.authenticated.retain(|id, _| pool.get(*id).is_some());
+        if self.authenticated.is_empty() || self.config.proxy.is_some() {
+            return None;
+        }
+        let port = url.port.unwrap_or(if url.is_https { 443 } else { 80 });
+        let id = self.pool.acquire_authenticated(&HostKey::new(&url.host, port, url.is_https), self.auth_identity)?;
+        let Some(mut connection) = self.authenticated.remove(&id) else {
+            self.pool.close(id);
+            return None;
+        };
+        if connection.set_deadline(self.deadline.clone()).is_err() {
+            self.pool.close(id);
+            return None;
+        }
+        
+        let mut timings = RequestTimings::default();
+        match self.send_and_receive_http1(&mut connection, req.clone(), None, &mut timings) {
+            Ok(response) => {
+                self.last_timings = Some(timings);
+                if closes_connection(&response) {
+                    self.pool.close(id);
+                } else {
+                    self.authenticated.insert(id, connection);
+                    self.pool.release(id);
+                }
+                Some(response)
+            }
+            Err(_) => {
+                self.pool.close(id);
+                None
+            }
+        }
+    }
+    
+    /// Connect straight to the origin for HTTP/1.1, which connection-based
+    /// authentication needs
+    #[cfg(feature = "integrated-auth")]
+    fn connect_http1(&self, url: &UrlParts, port: u16, timings: &mut RequestTimings) -> Result<HttpConnection, NetError> {
+        let stream = self.connect_tcp(&url.host, port, timings)?;
+        if !url.is_https {
+            return Ok(HttpConnection::Plain(stream));
+        }
+        let tls = self.start_tls(stream, &url.host, vec!["http/1.1".into()], timings)?;
+        Ok(HttpConnection::Tls(Box::new(tls)))
+    }
+    
+    /// Ask the proxy for a tunnel to the origin. A refusal, such as a 407
+    /// challenge, is returned as the response.
+    fn open_tunnel(&self, stream: &mut TcpConnection, url: &UrlParts, port: u16) -> Result<Option<Response>, NetError> {
+        let authority = format!("{}:{}", url.host, port);
+        let mut req = Http1Request::new("CONNECT", &authority).header("Host", &authority);
+        let proxy_auth = self.config.proxy.as_deref()
+            .and_then(|proxy| self.auth_cache.authorization(AuthTarget::Proxy, proxy, "CONNECT", &authority));
+        if let Some(value) = proxy_auth {
+            req = req.header("Proxy-Authorization", &value);
+        }
+        req.write_to(stream)
+            .map_err(|e| NetError::Network(format!("Proxy write failed: {}", e)))?;
+        
+        let resp = Http1Parser::parse(&mut BufReader::new(&mut *stream))
+            .map_err(|e| NetError::Network(format!("Proxy response failed: {}", e)))?;
+        if (200..300).contains(&resp.status) {
+            return Ok(None);
+        }
+        Ok(Some(Response {
+            status: resp.status,
+            headers: resp.headers,
+            body: resp.body,
+        }))
+    }
+    
+    fn execute_request(&mut self, url: &UrlParts, req: Http1Request, upload: Option<&Upload>) -> Result<Response, NetError> {
         let port = url.port.unwrap_or(if url.is_https { 443 } else { 80 });
         let origin = format!("{}:{}", url.host, port);
         
         // Check Alt-Svc cache for HTTP/3 support
-        if self.config.http3_enabled && url.is_https {
+        if self.config.http3_enabled && url.is_https && self.config.proxy.is_none() {
             if let Some(alt_entry) = self.alt_svc_cache.get_h3(&url.host) {
                 // Try HTTP/3
                 let h3_host = alt_entry.effective_host(&url.host);
@@ -295,29 +704,34 @@ impl HttpClient {
             }
         }
         
-        // Connect via TCP
-        let addr = format!("{}:{}", url.host, port);
-        let tcp_config = TcpConfig {
-            connect_timeout: self.config.connect_timeout,
-            read_timeout: Some(self.config.request_timeout),
-            write_timeout: Some(self.config.request_timeout),
-            ..Default::default()
-        };
+        let mut timings = RequestTimings::default();
         
-        let stream = TcpConnection::connect_with_config(&addr, tcp_config)
-            .map_err(|e| NetError::Network(format!("Connection failed: {}", e)))?;
+        // Resolve, then connect via TCP, to the proxy if there is one
+        let (connect_host, connect_port) = match self.config.proxy.as_deref().and_then(|p| p.rsplit_once(':')) {
+            Some((host, proxy_port)) => (host, proxy_port.parse().unwrap_or(8080)),
+            None => (url.host.as_str(), port),
+        };
+        let mut stream = self.connect_tcp(connect_host, connect_port, &mut timings)?;
         
         if url.is_https {
+            let refused = match self.config.proxy {
+                Some(_) => self.open_tunnel(&mut stream, url, port)?,
+                None => None,
+            };
+            if let Some(refused) = refused {
+                return Ok(refused);
+            }
+            
             // Upgrade to TLS
-            let tls = TlsStream::connect(stream, &url.host, TlsConfig::default())
-                .map_err(|e| NetError::Network(format!("TLS failed: {}", e)))?;
+            let tls = self.start_tls(stream, &url.host, TlsConfig::default().alpn_protocols, &mut timings)?;
             
             // Check ALPN for HTTP/2
             let response = if tls.is_h2() {
-                self.send_and_receive_h2(tls, &url, req)?
+                self.send_and_receive_h2(tls, url, req, upload, &mut timings)?
             } else {
-                self.send_and_receive_tls(tls, req)?
+                self.send_and_receive_http1(tls, req, upload, &mut timings)?
             };
+            self.last_timings = Some(timings);
             
             // Parse Alt-Svc header for future HTTP/3 discovery
             if self.config.http3_enabled {
@@ -332,10 +746,54 @@ impl HttpClient {
             
             Ok(response)
         } else {
-            self.send_and_receive_tcp(stream, req)
+            let response = self.send_and_receive_http1(stream, req, upload, &mut timings)?;
+            self.last_timings = Some(timings);
+            Ok(response)
         }
     }
     
+    /// Resolve `host` and open a TCP connection to it
+    fn connect_tcp(&self, host: &str, port: u16, timings: &mut RequestTimings) -> Result<TcpConnection, NetError> {
+        let started = Instant::now();
+        let socket_addr = resolve_within(host, port, &self.deadline)
+            .map_err(|e| self.deadline.error().unwrap_or_else(|| NetError::Network(format!("DNS lookup failed: {}", e))))?;
+        timings.dns_ms = Some(elapsed_ms(started));
+        
+        self.deadline.check()?;
+        let tcp_config = TcpConfig {
+            connect_timeout: self.deadline.limit(self.config.connect_timeout),
+            read_timeout: Some(self.config.request_timeout),
+            write_timeout: Some(self.config.request_timeout),
+            ..Default::default()
+        };
+        
+        let started = Instant::now();
+        let mut stream = TcpConnection::connect_to_addr(socket_addr, tcp_config)
+            .map_err(|e| self.deadline.error().unwrap_or_else(|| NetError::Network(format!("Connection failed: {}", e))))?;
+        stream.set_deadline(self.deadline.clone())
+            .map_err(|e| NetError::Network(format!("Connection failed: {}", e)))?;
+        timings.connect_ms = Some(elapsed_ms(started));
+        Ok(stream)
+    }
+    
+    /// Upgrade a connection to TLS, offering `alpn_protocols`
+    fn start_tls(&self, stream: TcpConnection, host: &str, alpn_protocols: Vec<String>, timings: &mut RequestTimings) -> Result<TlsStream, NetError> {
+        let started = Instant::now();
+        let tls_config = TlsConfig {
+            verify_certs: !self.config.certificate_overrides.iter().any(|h| h.eq_ignore_ascii_case(host)),
+            alpn_protocols,
+            client_certs: self.config.client_certs.clone(),
+            ..TlsConfig::default()
+        };
+        let tls = TlsStream::connect(stream, host, tls_config)
+            .map_err(|e| match TlsError::from_io(&e, host) {
+                Some(tls) => NetError::Tls(tls),
+                None => NetError::Network(format!("TLS failed: {}", e)),
+            })?;
+        timings.tls_ms = Some(elapsed_ms(started));
+        Ok(tls)
+    }
+    
     /// Try HTTP/3 connection (returns error if not available)
     fn try_http3(&self, _host: &str, _port: u16, _url: &UrlParts, _req: &Http1Request) -> Result<Response, NetError> {
         // HTTP/3 requires async UDP - for now, return error to fall back
@@ -362,32 +820,23 @@ impl HttpClient {
         headers
     }
     
-    fn send_and_receive_tcp(&self, mut stream: TcpConnection, req: Http1Request) -> Result<Response, NetError> {
-        // Send request
-        req.write_to(&mut stream)
-            .map_err(|e| NetError::Network(format!("Write failed: {}", e)))?;
-        
-        // Read response
-        let mut reader = BufReader::new(stream);
-        let resp = Http1Parser::parse(&mut reader)
-            .map_err(|e| NetError::Network(format!("Parse failed: {}", e)))?;
-        
-        Ok(Response {
-            status: resp.status,
-            headers: resp.headers,
-            body: resp.body,
-        })
-    }
-    
-    fn send_and_receive_tls(&self, mut stream: TlsStream, req: Http1Request) -> Result<Response, NetError> {
+    fn send_and_receive_http1<S: Read + Write>(&self, mut stream: S, req: Http1Request, upload: Option<&Upload>, timings: &mut RequestTimings) -> Result<Response, NetError> {
         // Send request
+        let started = Instant::now();
         req.write_to(&mut stream)
             .map_err(|e| NetError::Network(format!("Write failed: {}", e)))?;
+        if let Some(upload) = upload {
+            upload.write_http1(&mut stream)
+                .map_err(|e| NetError::Network(e.to_string()))?;
+        }
+        timings.send_ms = elapsed_ms(started);
         
         // Read response
-        let mut reader = BufReader::new(stream);
+        let sent = Instant::now();
+        let mut reader = BufReader::new(TimedReader::new(stream));
         let resp = Http1Parser::parse(&mut reader)
             .map_err(|e| NetError::Network(format!("Parse failed: {}", e)))?;
+        reader.get_ref().record(sent, timings);
         
         Ok(Response {
             status: resp.status,
@@ -397,7 +846,9 @@ impl HttpClient {
     }
     
     /// Send request using HTTP/2
-    fn send_and_receive_h2(&self, mut stream: TlsStream, url: &UrlParts, req: Http1Request) -> Result<Response, NetError> {
+    fn send_and_receive_h2(&self, mut stream: TlsStream, url: &UrlParts, req: Http1Request, upload: Option<&Upload>, timings: &mut RequestTimings) -> Result<Response, NetError> {
+        let started = Instant::now();
+        
         // Create HTTP/2 connection
         let mut h2 = Http2Connection::new_client();
         
@@ -405,9 +856,9 @@ impl HttpClient {
         h2.send_preface(&mut stream)
             .map_err(|e| NetError::Network(format!("H2 preface failed: {}", e)))?;
         
-        // Send request
+        // Send request; HTTP/2 frames bodies itself, so no chunked coding
         let headers: Vec<(String, String)> = req.headers.iter()
-            .filter(|(n, _)| !n.starts_with(':'))
+            .filter(|(n, _)| !n.starts_with(':') && !n.eq_ignore_ascii_case("transfer-encoding"))
             .cloned()
             .collect();
         
@@ -417,7 +868,7 @@ impl HttpClient {
             &url.path_and_query(),
             &url.host_with_port(),
             &headers,
-            req.body.is_none(),
+            req.body.is_none() && upload.is_none(),
         ).map_err(|e| NetError::Network(format!("H2 request failed: {}", e)))?;
         
         // Send body if present
@@ -425,8 +876,16 @@ impl HttpClient {
             h2.send_data(&mut stream, stream_id, body, true)
                 .map_err(|e| NetError::Network(format!("H2 data failed: {}", e)))?;
         }
+        if let Some(upload) = upload {
+            upload.write_h2(&mut h2, &mut stream, stream_id)
+                .map_err(|e| NetError::Network(e.to_string()))?;
+        }
+        
+        timings.send_ms = elapsed_ms(started);
         
         // Read response frames
+        let sent = Instant::now();
+        let mut first_byte = None;
         let mut response_headers = Vec::new();
         let mut response_body = Vec::new();
         let mut response_status = 200u16;
@@ -445,6 +904,7 @@ impl HttpClient {
                         .map_err(|e| NetError::Network(format!("H2 settings ack failed: {}", e)))?;
                 }
                 Some(Http2Event::Headers { stream_id: sid, headers, end_stream }) if sid == stream_id => {
+                    first_byte.get_or_insert_with(Instant::now);
                     // Parse status from pseudo-header
                     for (name, value) in &headers {
                         if name == ":status" {
@@ -480,6 +940,10 @@ impl HttpClient {
             }
         }
         
+        let first_byte = first_byte.unwrap_or(sent);
+        timings.wait_ms = elapsed_ms(sent) - elapsed_ms(first_byte);
+        timings.receive_ms = elapsed_ms(first_byte);
+        
         Ok(Response {
             status: response_status,
             headers: response_headers,
@@ -510,6 +974,12 @@ impl HttpClient {
         }
     }
     
+    /// Phase timings of the last network exchange; with redirects, of
+    /// the final hop
+    pub fn last_timings(&self) -> Option<RequestTimings> {
+        self.last_timings
+    }
+    
     /// Get cookie jar reference
     pub fn cookies(&self) -> &CookieJar {
         &self.cookies
@@ -527,6 +997,64 @@ impl Default for HttpClient {
     }
 }
 
+/// An open HTTP/1.1 connection, plain or over TLS
+#[cfg(feature = "integrated-auth")]
+enum HttpConnection {
+    Plain(TcpConnection),
+    Tls(Box<TlsStream>),
+}
+
+#[cfg(feature = "integrated-auth")]
+impl HttpConnection {
+    fn set_deadline(&mut self, deadline: Deadline) -> io::Result<()> {
+        match self {
+            Self::Plain(stream) => stream.set_deadline(deadline),
+            Self::Tls(stream) => stream.set_deadline(deadline),
+        }
+    }
+}
+
+#[cfg(feature = "integrated-auth")]
+impl Read for HttpConnection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.read(buf),
+            Self::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+#[cfg(feature = "integrated-auth")]
+impl Write for HttpConnection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.write(buf),
+            Self::Tls(stream) => stream.write(buf),
+        }
+    }
+    
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(stream) => stream.flush(),
+            Self::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+/// The server will close the connection after this response
+#[cfg(feature = "integrated-auth")]
+fn closes_connection(response: &Response) -> bool {
+    response.headers.iter()
+        .any(|(name, value)| name.eq_ignore_ascii_case("connection") && value.eq_ignore_ascii_case("close"))
+}
+
+/// Body of a request: bytes in memory, or streamed from an upload source
+#[derive(Debug, Clone)]
+enum RequestBody {
+    Bytes(Vec<u8>),
+    Stream(Upload),
+}
+
 /// Simple URL parsing (for internal use)
 #[derive(Debug)]
 struct UrlParts {
@@ -586,6 +1114,16 @@ impl UrlParts {
         }
     }
     
+    /// `scheme://host[:port]`, the key credentials are cached under
+    fn origin(&self) -> String {
+        format!("{}://{}", if self.is_https { "https" } else { "http" }, self.host_with_port())
+    }
+    
+    /// The full URL, as sent to a proxy
+    fn absolute(&self) -> String {
+        format!("{}{}", self.origin(), self.path_and_query())
+    }
+    
     fn host_with_port(&self) -> String {
         match self.port {
             Some(p) => format!("{}:{}", self.host, p),
@@ -631,6 +1169,11 @@ pub mod blocking {
         ) -> Result<Response, NetError> {
             self.inner.request(method, url, headers, body)
         }
+        
+        /// Phase timings of the last network exchange
+        pub fn last_timings(&self) -> Option<RequestTimings> {
+            self.inner.last_timings()
+        }
     }
     
     impl Default for Client {
@@ -660,6 +1203,54 @@ pub mod blocking {
             self
         }
         
+        pub fn save_data(mut self, enabled: bool) -> Self {
+            self.inner = self.inner.save_data(enabled);
+            self
+        }
+        
+        /// Header sent with every request, redirects included
+        pub fn default_header(mut self, name: &str, value: &str) -> Self {
+            self.inner = self.inner.default_header(name, value);
+            self
+        }
+        
+        /// Connect to `host` even if its certificate fails validation
+        pub fn allow_invalid_certificate(mut self, host: &str) -> Self {
+            self.inner = self.inner.allow_invalid_certificate(host);
+            self
+        }
+        
+        /// Answer servers asking for a client certificate from `manager`
+        pub fn client_certificates(mut self, manager: Arc<ClientCertManager>) -> Self {
+            self.inner = self.inner.client_certificates(manager);
+            self
+        }
+        
+        /// Send requests through an HTTP proxy
+        pub fn proxy(mut self, proxy: &str) -> Self {
+            self.inner = self.inner.proxy(proxy);
+            self
+        }
+        
+        /// Share credentials accepted by origins and proxies with other clients
+        pub fn auth_cache(mut self, cache: AuthCache) -> Self {
+            self.inner = self.inner.auth_cache(cache);
+            self
+        }
+        
+        /// Ask `provider` for credentials when a server or proxy challenges
+        pub fn credential_provider(mut self, provider: Arc<dyn CredentialProvider>) -> Self {
+            self.inner = self.inner.credential_provider(provider);
+            self
+        }
+        
+        /// Answer Negotiate and NTLM challenges with the user's login
+        #[cfg(feature = "integrated-auth")]
+        pub fn integrated_auth(mut self, auth: IntegratedAuth) -> Self {
+            self.inner = self.inner.integrated_auth(auth);
+            self
+        }
+        
         pub fn build(self) -> Result<Client, NetError> {
             Ok(Client {
                 inner: self.inner.build(),
@@ -700,10 +1291,22 @@ mod tests {
         let client = HttpClient::builder()
             .user_agent("TestAgent/1.0")
             .max_redirects(5)
+            .save_data(true)
             .build();
         
         assert_eq!(client.config.user_agent, "TestAgent/1.0");
         assert_eq!(client.config.max_redirects, 5);
+        assert!(client.config.save_data);
+    }
+    
+    #[test]
+    fn test_proxy_request_target() {
+        let client = HttpClient::builder().proxy("http://Proxy.local:3128/").build();
+        assert_eq!(client.config.proxy.as_deref(), Some("proxy.local:3128"));
+        
+        let url = UrlParts::parse("http://example.com:8080/a?b=c").unwrap();
+        assert_eq!(url.origin(), "http://example.com:8080");
+        assert_eq!(url.absolute(), "http://example.com:8080/a?b=c");
     }
     
     #[test]
@@ -720,4 +1323,67 @@ mod tests {
             "http://example.com/new/path"
         );
     }
+    
+    #[cfg(feature = "integrated-auth")]
+    #[test]
+    fn test_integrated_auth_keeps_connection() {
+        use crate::negotiate::{AuthBackend, IntegratedAuthError, IntegratedAuthPolicy, SecurityContext};
+        use std::net::TcpListener;
+        
+        struct Ntlm;
+        struct NtlmContext(bool);
+        
+        impl SecurityContext for NtlmContext {
+            fn step(&mut self, server_token: Option<&[u8]>) -> Result<Vec<u8>, IntegratedAuthError> {
+                let started = std::mem::replace(&mut self.0, true);
+                match (started, server_token) {
+                    (false, None) => Ok(b"negotiate".to_vec()),
+                    (true, Some(b"challenge")) => Ok(b"authenticate".to_vec()),
+                    _ => Err(IntegratedAuthError::Failed("unexpected token".to_string())),
+                }
+            }
+        }
+        
+        impl AuthBackend for Ntlm {
+            fn start(&self, _scheme: IntegratedScheme, _host: &str) -> Result<Box<dyn SecurityContext>, IntegratedAuthError> {
+                Ok(Box::new(NtlmContext(false)))
+            }
+        }
+        
+        fn read_head(stream: &mut impl Read) -> String {
+            let mut head = Vec::new();
+            let mut byte = [0u8];
+            while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap() == 1 {
+                head.push(byte[0]);
+            }
+            String::from_utf8(head).unwrap()
+        }
+        
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut first, _) = listener.accept().unwrap();
+            assert!(!read_head(&mut first).contains("Authorization"));
+            first.write_all(b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: NTLM\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+            drop(first);
+            
+            // The handshake and the next request share one connection
+            let (mut stream, _) = listener.accept().unwrap();
+            assert!(read_head(&mut stream).contains("Authorization: NTLM bmVnb3RpYXRl"));
+            stream.write_all(b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: NTLM Y2hhbGxlbmdl\r\nContent-Length: 0\r\n\r\n").unwrap();
+            assert!(read_head(&mut stream).contains("Authorization: NTLM YXV0aGVudGljYXRl"));
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+            assert!(!read_head(&mut stream).contains("Authorization"));
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nagain").unwrap();
+        });
+        
+        let policy = IntegratedAuthPolicy::new().with_allowed_servers("127.0.0.1");
+        let mut client = HttpClient::builder()
+            .integrated_auth(IntegratedAuth::new(policy, Arc::new(Ntlm)))
+            .build();
+        let url = format!("http://127.0.0.1:{}/", port);
+        assert_eq!(client.get(&url).unwrap().text().as_deref(), Some("ok"));
+        assert_eq!(client.get(&url).unwrap().text().as_deref(), Some("again"));
+        server.join().unwrap();
+    }
 }
@@ -70,6 +70,9 @@ pub struct PooledConnection {
     pub request_count: u32,
     /// Is pre-warmed
     pub is_prewarmed: bool,
+    /// Login a connection-based scheme (NTLM, Negotiate) authenticated the
+    /// connection with; only requests made with that login may use it
+    pub auth_identity: Option<u64>,
 }
 
 impl PooledConnection {
@@ -86,6 +89,7 @@ impl PooledConnection {
             last_used: now,
             request_count: 0,
             is_prewarmed: false,
+            auth_identity: None,
         }
     }
     
@@ -263,7 +267,7 @@ impl ConnectionPool {
         if let Some(conn_ids) = self.by_host.get(host) {
             for &id in conn_ids {
                 if let Some(conn) = self.connections.get_mut(&id) {
-                    if conn.is_usable() {
+                    if conn.is_usable() && conn.auth_identity.is_none() {
                         conn.acquire();
                         self.stats.connections_reused += 1;
                         
@@ -299,6 +303,40 @@ impl ConnectionPool {
         AcquireResult::Created(id)
     }
     
+    /// Get an idle connection to a host already authenticated with
+    /// `identity`'s login
+    pub fn acquire_authenticated(&mut self, host: &HostKey, identity: u64) -> Option<ConnId> {
+        let id = self.by_host.get(host)?.iter()
+            .find(|id| self.connections.get(id).is_some_and(|c| c.is_usable() && c.auth_identity == Some(identity)))
+            .copied()?;
+        if let Some(conn) = self.connections.get_mut(&id) {
+            conn.acquire();
+            self.stats.connections_reused += 1;
+        }
+        Some(id)
+    }
+    
+    /// Create a connection for `identity`'s login to authenticate. Always
+    /// a new one: authenticating an idle connection other requests share
+    /// would lend them the login.
+    pub fn create_authenticated(&mut self, host: &HostKey, identity: u64) -> AcquireResult {
+        let host_count = self.by_host.get(host).map(|v| v.len()).unwrap_or(0);
+        if host_count >= self.config.max_per_host {
+            return AcquireResult::WaitForConnection;
+        }
+        if self.connections.len() >= self.config.max_total {
+            self.evict_stale();
+            if self.connections.len() >= self.config.max_total {
+                return AcquireResult::PoolExhausted;
+            }
+        }
+        let id = self.create_connection(host);
+        if let Some(conn) = self.connections.get_mut(&id) {
+            conn.auth_identity = Some(identity);
+        }
+        AcquireResult::Created(id)
+    }
+    
     /// Create a new connection
     fn create_connection(&mut self, host: &HostKey) -> ConnId {
         let id = self.next_id;
@@ -516,4 +554,22 @@ mod tests {
         
         assert_eq!(pool.stats().prewarmed_hits, 1);
     }
+    
+    #[test]
+    fn test_auth_affinity() {
+        let mut pool = ConnectionPool::default();
+        let host = HostKey::new("intranet", 80, false);
+        
+        let AcquireResult::Created(shared) = pool.acquire(&host) else { panic!("Expected Created") };
+        pool.release(shared);
+        let AcquireResult::Created(bound) = pool.create_authenticated(&host, 1) else { panic!("Expected Created") };
+        assert_ne!(bound, shared);
+        assert_eq!(pool.acquire_authenticated(&host, 1), None);
+        pool.release(bound);
+        
+        // Unauthenticated requests and other logins don't get the bound one
+        assert!(matches!(pool.acquire(&host), AcquireResult::Reused(id) if id == shared));
+        assert_eq!(pool.acquire_authenticated(&host, 2), None);
+        assert_eq!(pool.acquire_authenticated(&host, 1), Some(bound));
+    }
 }
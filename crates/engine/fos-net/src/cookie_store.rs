@@ -0,0 +1,310 @@
+//! Cookie Persistence
+//!
+//! Saves cookie jars to disk as an SQLite dump: a `cookies` table with one
+//! `INSERT` per cookie, the same text `sqlite3 .dump` produces, so a store
+//! can be imported with `sqlite3 cookies.db < cookies.sql`. Writes go to a
+//! temporary file that is synced and renamed over the store, so a crash
+//! leaves either the old or the new contents.
+//!
+//! Session cookies are dropped on save unless session restore is enabled,
+//! in which case they come back on the next start like persistent ones.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cookies::{Cookie, CookieJar, PartitionKey, PartitionedCookieJar, SameSite};
+
+const TABLE: &str = "CREATE TABLE cookies(name TEXT NOT NULL, value TEXT NOT NULL, host TEXT NOT NULL, \
+path TEXT NOT NULL, expiry INTEGER, creation_time INTEGER NOT NULL, last_accessed INTEGER NOT NULL, \
+is_secure INTEGER NOT NULL, is_http_only INTEGER NOT NULL, same_site INTEGER NOT NULL, \
+is_partitioned INTEGER NOT NULL, partition_key TEXT);";
+
+const INSERT: &str = "INSERT INTO cookies VALUES(";
+
+/// SQL value in a stored row
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Integer(i64),
+    Text(String),
+}
+
+impl Value {
+    fn text(&self) -> Option<&str> {
+        match self {
+            Self::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn integer(&self) -> Option<i64> {
+        match self {
+            Self::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// On-disk cookie store
+#[derive(Debug, Clone)]
+pub struct CookieStore {
+    path: PathBuf,
+    /// Keep session cookies across restarts (session restore)
+    keep_session_cookies: bool,
+}
+
+impl CookieStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            keep_session_cookies: false,
+        }
+    }
+
+    /// Keep session cookies across restarts
+    pub fn keep_session_cookies(mut self, keep: bool) -> Self {
+        self.keep_session_cookies = keep;
+        self
+    }
+
+    /// Store location
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Save a cookie jar, replacing the store atomically
+    pub fn save(&self, jar: &CookieJar) -> io::Result<()> {
+        self.write(jar.iter().map(|c| (c, None)))
+    }
+
+    /// Save a partitioned cookie jar, replacing the store atomically
+    pub fn save_partitioned(&self, jar: &PartitionedCookieJar) -> io::Result<()> {
+        let unpartitioned = jar.unpartitioned().iter().map(|c| (c, None));
+        let partitioned = jar
+            .partitions()
+            .flat_map(|(key, jar)| jar.iter().map(move |c| (c, Some(key))));
+        self.write(unpartitioned.chain(partitioned))
+    }
+
+    /// Load a cookie jar; a missing store gives an empty jar
+    pub fn load(&self) -> io::Result<CookieJar> {
+        let mut jar = CookieJar::new();
+        for (cookie, _) in self.read()? {
+            jar.restore(cookie);
+        }
+        Ok(jar)
+    }
+
+    /// Load a partitioned cookie jar; a missing store gives an empty jar
+    pub fn load_partitioned(&self) -> io::Result<PartitionedCookieJar> {
+        let mut jar = PartitionedCookieJar::new();
+        for (cookie, key) in self.read()? {
+            match key {
+                Some(key) => jar.restore(cookie, key),
+                None => jar.unpartitioned_mut().restore(cookie),
+            }
+        }
+        Ok(jar)
+    }
+
+    fn write<'a>(&self, cookies: impl Iterator<Item = (&'a Cookie, Option<&'a PartitionKey>)>) -> io::Result<()> {
+        let mut sql = String::from("PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\n");
+        sql.push_str(TABLE);
+        sql.push('\n');
+        for (cookie, key) in cookies {
+            if cookie.is_expired() || (cookie.expires.is_none() && !self.keep_session_cookies) {
+                continue;
+            }
+            let expiry = cookie.expires.map_or("NULL".to_string(), |e| e.to_string());
+            let same_site = match cookie.same_site {
+                SameSite::None => 0,
+                SameSite::Lax => 1,
+                SameSite::Strict => 2,
+            };
+            let partition = key.map_or("NULL".to_string(), |k| quote(&k.top_level_site));
+            sql.push_str(&format!(
+                "{}{},{},{},{},{},{},{},{},{},{},{},{});\n",
+                INSERT,
+                quote(&cookie.name),
+                quote(&cookie.value),
+                quote(&cookie.domain),
+                quote(&cookie.path),
+                expiry,
+                cookie.creation_time,
+                cookie.last_access,
+                cookie.secure as u8,
+                cookie.http_only as u8,
+                same_site,
+                cookie.partitioned as u8,
+                partition,
+            ));
+        }
+        sql.push_str("COMMIT;\n");
+
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        {
+            let mut file = File::create(&temp)?;
+            file.write_all(sql.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp, &self.path)?;
+        // Make the rename itself durable
+        let dir = self.path.parent().filter(|d| !d.as_os_str().is_empty());
+        if let Some(Ok(dir)) = dir.map(File::open) {
+            let _ = dir.sync_all();
+        }
+        Ok(())
+    }
+
+    fn read(&self) -> io::Result<Vec<(Cookie, Option<PartitionKey>)>> {
+        let sql = match fs::read_to_string(&self.path) {
+            Ok(sql) => sql,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut rows = Vec::new();
+        let mut rest = sql.as_str();
+        while let Some(pos) = rest.find(INSERT) {
+            rest = &rest[pos + INSERT.len()..];
+            let Some((values, after)) = parse_row(rest) else {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed cookie row"));
+            };
+            rest = after;
+            if let Some(row) = row_to_cookie(&values) {
+                let is_session = row.0.expires.is_none();
+                if !is_session || self.keep_session_cookies {
+                    rows.push(row);
+                }
+            }
+        }
+        Ok(rows)
+    }
+}
+
+/// SQL string literal
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// Parse the values of an `INSERT` after its opening parenthesis, returning
+/// them with the text after the closing `);`
+fn parse_row(input: &str) -> Option<(Vec<Value>, &str)> {
+    let mut values = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    loop {
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let (start, c) = *chars.peek()?;
+        let value = if c == '\'' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                let (_, c) = chars.next()?;
+                if c != '\'' {
+                    text.push(c);
+                } else if chars.next_if(|(_, c)| *c == '\'').is_some() {
+                    text.push('\'');
+                } else {
+                    break;
+                }
+            }
+            Value::Text(text)
+        } else {
+            let mut end = start;
+            while let Some((i, c)) = chars.next_if(|(_, c)| *c != ',' && *c != ')') {
+                end = i + c.len_utf8();
+            }
+            let token = input[start..end].trim();
+            if token.eq_ignore_ascii_case("NULL") {
+                Value::Null
+            } else {
+                Value::Integer(token.parse().ok()?)
+            }
+        };
+        values.push(value);
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        match chars.next()? {
+            (_, ',') => continue,
+            (i, ')') => {
+                let rest = input[i + 1..].strip_prefix(';')?;
+                return Some((values, rest));
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn row_to_cookie(values: &[Value]) -> Option<(Cookie, Option<PartitionKey>)> {
+    let [name, value, host, path, expiry, created, accessed, secure, http_only, same_site, partitioned, key] = values else {
+        return None;
+    };
+    let mut cookie = Cookie::new(name.text()?, value.text()?, host.text()?, path.text()?);
+    cookie.expires = expiry.integer().map(|e| e as u64);
+    cookie.creation_time = created.integer()? as u64;
+    cookie.last_access = accessed.integer()? as u64;
+    cookie.secure = secure.integer()? != 0;
+    cookie.http_only = http_only.integer()? != 0;
+    cookie.same_site = match same_site.integer()? {
+        0 => SameSite::None,
+        2 => SameSite::Strict,
+        _ => SameSite::Lax,
+    };
+    cookie.partitioned = partitioned.integer()? != 0;
+    Some((cookie, key.text().map(PartitionKey::new)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("fos-cookies-{}-{}.sql", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = temp_path("round-trip");
+        let mut jar = CookieJar::new();
+        jar.add_from_header("session=1", "example.com");
+        jar.add_from_header("pref=it's; Max-Age=3600; Secure; SameSite=Strict", "example.com");
+
+        CookieStore::new(&path).save(&jar).unwrap();
+        let sql = fs::read_to_string(&path).unwrap();
+        assert!(sql.starts_with("PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\nCREATE TABLE cookies("));
+        assert!(sql.contains("'it''s'"));
+
+        // Session cookies do not survive a restart
+        let loaded = CookieStore::new(&path).load().unwrap();
+        assert_eq!(loaded.len(), 1);
+        let pref = loaded.get_cookies("example.com", "/", true)[0].clone();
+        assert_eq!(pref.value, "it's");
+        assert_eq!(pref.same_site, SameSite::Strict);
+        assert_eq!(pref.creation_time, jar.get_cookies("example.com", "/", true)[1].creation_time);
+
+        // Unless session restore keeps them
+        let store = CookieStore::new(&path).keep_session_cookies(true);
+        store.save(&jar).unwrap();
+        assert_eq!(store.load().unwrap().len(), 2);
+        fs::remove_file(&path).unwrap();
+        assert!(store.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_partitioned_round_trip() {
+        let path = temp_path("partitioned");
+        let mut jar = PartitionedCookieJar::new();
+        let partition = PartitionKey::new("https://site-a.com");
+        jar.add_from_header("id=A; Max-Age=60; Secure; Partitioned", "tracker.com", Some(&partition));
+        jar.add_from_header("first=1; Max-Age=60", "site-a.com", None);
+
+        let store = CookieStore::new(&path);
+        store.save_partitioned(&jar).unwrap();
+        let loaded = store.load_partitioned().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.get_cookies("tracker.com", "/", true, Some(&partition)).len(), 1);
+        assert!(loaded.get_cookies("tracker.com", "/", true, None).is_empty());
+    }
+}
@@ -2,10 +2,14 @@
 //!
 //! Cookie jar with domain/path matching, StringInterner for memory efficiency,
 //! and cookie partitioning (CHIPS) for cross-site tracking prevention.
+//! Cookies are indexed by registrable domain and capped per domain and in
+//! total, evicting the least recently used first.
 
 use std::collections::HashMap;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use fos_dom::url::PublicSuffixList;
+
 /// Cookie attributes
 #[derive(Debug, Clone)]
 pub struct Cookie {
@@ -27,6 +31,10 @@ pub struct Cookie {
     pub same_site: SameSite,
     /// Partitioned cookie flag (CHIPS)
     pub partitioned: bool,
+    /// When the cookie was first set, in microseconds since the epoch
+    pub creation_time: u64,
+    /// When the cookie was last set or sent, in microseconds since the epoch
+    pub last_access: u64,
 }
 
 /// SameSite attribute values
@@ -54,6 +62,8 @@ impl Cookie {
             http_only: false,
             same_site: SameSite::Lax,
             partitioned: false,
+            creation_time: 0,
+            last_access: 0,
         }
     }
     
@@ -69,6 +79,8 @@ impl Cookie {
             http_only: false,
             same_site: SameSite::None, // Partitioned cookies typically use SameSite=None
             partitioned: true,
+            creation_time: 0,
+            last_access: 0,
         }
     }
     
@@ -122,6 +134,20 @@ impl Cookie {
         self.matches_domain(domain) && self.matches_path(path)
     }
     
+    /// Check the SameSite attribute for a request to `request_host` made
+    /// from a document on `site_for_cookies`; Lax cookies still go with
+    /// cross-site top-level navigations
+    pub fn same_site_allows(&self, request_host: &str, site_for_cookies: &str, top_level_navigation: bool) -> bool {
+        if PublicSuffixList::current().is_same_site(request_host, site_for_cookies) {
+            return true;
+        }
+        match self.same_site {
+            SameSite::None => true,
+            SameSite::Lax => top_level_navigation,
+            SameSite::Strict => false,
+        }
+    }
+    
     /// Serialize to Cookie header format (name=value)
     pub fn serialize(&self) -> String {
         format!("{}={}", self.name, self.value)
@@ -161,22 +187,11 @@ impl PartitionKey {
         })
     }
     
-    /// Extract eTLD+1 from host (simplified implementation)
+    /// Extract eTLD+1 from host using the public suffix list
     fn get_etld_plus_1(host: &str) -> String {
-        let parts: Vec<&str> = host.split('.').collect();
-        
-        // Handle IP addresses  
-        if parts.iter().all(|p| p.parse::<u8>().is_ok()) {
-            return host.to_string();
-        }
-        
-        // Simple eTLD+1 extraction (last 2 parts)
-        // Production would use public suffix list
-        if parts.len() >= 2 {
-            parts[parts.len() - 2..].join(".")
-        } else {
-            host.to_string()
-        }
+        PublicSuffixList::current()
+            .registrable_domain(host)
+            .unwrap_or_else(|| host.to_string())
     }
 }
 
@@ -200,6 +215,8 @@ pub fn parse_set_cookie(header: &str, request_domain: &str) -> Option<Cookie> {
         http_only: false,
         same_site: SameSite::Lax,
         partitioned: false,
+        creation_time: 0,
+        last_access: 0,
     };
     
     // Parse attributes
@@ -220,12 +237,13 @@ pub fn parse_set_cookie(header: &str, request_domain: &str) -> Option<Cookie> {
         } else if let Some(value) = part.strip_prefix("Path=").or_else(|| part.strip_prefix("path=")) {
             cookie.path = value.trim().to_string();
         } else if let Some(value) = part.strip_prefix("Max-Age=").or_else(|| part.strip_prefix("max-age=")) {
-            if let Ok(seconds) = value.trim().parse::<u64>() {
+            if let Ok(seconds) = value.trim().parse::<i64>() {
                 let now = SystemTime::now()
                     .duration_since(UNIX_EPOCH)
                     .unwrap_or(Duration::ZERO)
                     .as_secs();
-                cookie.expires = Some(now + seconds);
+                // Zero or negative expires the cookie at once
+                cookie.expires = Some(if seconds <= 0 { 0 } else { now + seconds as u64 });
             }
         } else if let Some(value) = part.strip_prefix("SameSite=").or_else(|| part.strip_prefix("samesite=")) {
             cookie.same_site = match value.trim().to_lowercase().as_str() {
@@ -239,11 +257,61 @@ pub fn parse_set_cookie(header: &str, request_domain: &str) -> Option<Cookie> {
     Some(cookie)
 }
 
+/// Parse a Set-Cookie header and check its Domain attribute against the
+/// request: it must cover the request host and must not be a public suffix
+pub fn parse_set_cookie_for_request(header: &str, request_domain: &str) -> Option<Cookie> {
+    let mut cookie = parse_set_cookie(header, request_domain)?;
+    let domain = cookie.domain.trim_start_matches('.').to_ascii_lowercase();
+    let request_domain = request_domain.to_ascii_lowercase();
+    if domain == request_domain {
+        return Some(cookie);
+    }
+    if PublicSuffixList::current().is_public_suffix(&domain) || !request_domain.ends_with(&format!(".{}", domain)) {
+        return None;
+    }
+    // A Domain attribute covers subdomains
+    cookie.domain = format!(".{}", domain);
+    Some(cookie)
+}
+
+/// Storage caps for a cookie jar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CookieLimits {
+    /// Cookies kept per registrable domain
+    pub per_domain: usize,
+    /// Cookies kept in total
+    pub total: usize,
+}
+
+impl Default for CookieLimits {
+    fn default() -> Self {
+        Self { per_domain: 180, total: 3000 }
+    }
+}
+
 /// Cookie jar for storing cookies
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct CookieJar {
-    /// Cookies indexed by domain
+    /// Cookies indexed by registrable domain
     cookies: HashMap<String, Vec<Cookie>>,
+    /// Storage caps
+    limits: CookieLimits,
+    /// Last timestamp handed out, so access times are strictly increasing
+    clock: u64,
+    /// Public suffix list generation the index was keyed with
+    psl_generation: u64,
+}
+
+/// Index key for a cookie domain or request host
+fn domain_key(domain: &str) -> String {
+    let host = domain.trim_start_matches('.').to_ascii_lowercase();
+    PublicSuffixList::current().registrable_domain(&host).unwrap_or(host)
+}
+
+impl Default for CookieJar {
+    fn default() -> Self {
+        Self::with_limits(CookieLimits::default())
+    }
 }
 
 impl CookieJar {
@@ -251,39 +319,132 @@ impl CookieJar {
         Self::default()
     }
     
+    /// Create with storage caps
+    pub fn with_limits(limits: CookieLimits) -> Self {
+        Self {
+            cookies: HashMap::new(),
+            limits,
+            clock: 0,
+            // Empty, so keyed with whichever list is in use
+            psl_generation: PublicSuffixList::generation(),
+        }
+    }
+    
+    /// Storage caps
+    pub fn limits(&self) -> CookieLimits {
+        self.limits
+    }
+    
+    /// Current time in microseconds, strictly after the previous stamp
+    fn stamp(&mut self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_micros() as u64;
+        self.clock = now.max(self.clock + 1);
+        self.clock
+    }
+    
+    /// Whether the index is keyed with the public suffix list in use
+    fn index_is_current(&self) -> bool {
+        self.psl_generation == PublicSuffixList::generation()
+    }
+    
+    /// Re-key the index after a new public suffix list was installed
+    fn reindex(&mut self) {
+        let generation = PublicSuffixList::generation();
+        if self.psl_generation == generation {
+            return;
+        }
+        self.psl_generation = generation;
+        let cookies: Vec<Cookie> = self.cookies.drain().flat_map(|(_, v)| v).collect();
+        for cookie in cookies {
+            self.cookies.entry(domain_key(&cookie.domain)).or_default().push(cookie);
+        }
+    }
+    
     /// Add a cookie to the jar
-    pub fn add(&mut self, cookie: Cookie) {
-        let domain = cookie.domain.clone();
-        let cookies = self.cookies.entry(domain).or_default();
-        
-        // Remove existing cookie with same name/path
-        cookies.retain(|c| !(c.name == cookie.name && c.path == cookie.path));
+    pub fn add(&mut self, mut cookie: Cookie) {
+        self.reindex();
+        let now = self.stamp();
+        let key = domain_key(&cookie.domain);
+        let cookies = self.cookies.entry(key.clone()).or_default();
+        
+        // Replace an existing cookie with same name/domain/path, keeping
+        // its creation time
+        cookie.creation_time = now;
+        if let Some(pos) = cookies.iter().position(|c| c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path) {
+            cookie.creation_time = cookies.remove(pos).creation_time;
+        }
+        cookie.last_access = now;
         
         // Only add if not already expired
         if !cookie.is_expired() {
             cookies.push(cookie);
+            self.evict(&key);
         }
     }
     
+    /// Add a stored cookie, keeping its timestamps
+    pub fn restore(&mut self, cookie: Cookie) {
+        if cookie.is_expired() {
+            return;
+        }
+        self.reindex();
+        self.clock = self.clock.max(cookie.last_access).max(cookie.creation_time);
+        let key = domain_key(&cookie.domain);
+        let cookies = self.cookies.entry(key.clone()).or_default();
+        cookies.retain(|c| !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path));
+        cookies.push(cookie);
+        self.evict(&key);
+    }
+    
+    /// Drop expired cookies, then the least recently used ones over the
+    /// per-domain and total caps
+    fn evict(&mut self, key: &str) {
+        if let Some(cookies) = self.cookies.get_mut(key) {
+            cookies.retain(|c| !c.is_expired());
+            while cookies.len() > self.limits.per_domain {
+                let Some(oldest) = Self::least_recent(cookies) else { break };
+                cookies.swap_remove(oldest);
+            }
+        }
+        while self.len() > self.limits.total {
+            let oldest = self
+                .cookies
+                .iter()
+                .filter_map(|(k, cookies)| Self::least_recent(cookies).map(|i| (cookies[i].last_access, k.clone(), i)))
+                .min();
+            let Some((_, k, i)) = oldest else { break };
+            if let Some(cookies) = self.cookies.get_mut(&k) {
+                cookies.swap_remove(i);
+            }
+        }
+        self.cookies.retain(|_, v| !v.is_empty());
+    }
+    
+    fn least_recent(cookies: &[Cookie]) -> Option<usize> {
+        (0..cookies.len()).min_by_key(|&i| cookies[i].last_access)
+    }
+    
     /// Add from Set-Cookie header
     pub fn add_from_header(&mut self, header: &str, request_domain: &str) {
-        if let Some(cookie) = parse_set_cookie(header, request_domain) {
+        if let Some(cookie) = parse_set_cookie_for_request(header, request_domain) {
             self.add(cookie);
         }
     }
     
-    /// Get cookies for a request
+    /// Get cookies for a request, longest paths first and then oldest
+    /// first. Only the bucket of the host's registrable domain is searched,
+    /// unless the index predates the public suffix list in use
     pub fn get_cookies(&self, domain: &str, path: &str, is_secure: bool) -> Vec<&Cookie> {
-        let mut result = Vec::new();
-        
-        for (_, cookies) in &self.cookies {
-            for cookie in cookies {
-                if cookie.matches(domain, path, is_secure) {
-                    result.push(cookie);
-                }
-            }
-        }
-        
+        let mut result: Vec<&Cookie> = if self.index_is_current() {
+            self.cookies.get(&domain_key(domain)).into_iter().flatten().collect()
+        } else {
+            self.iter().collect()
+        };
+        result.retain(|c| c.matches(domain, path, is_secure));
+        result.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.creation_time.cmp(&b.creation_time)));
         result
     }
     
@@ -301,6 +462,25 @@ impl CookieJar {
         }
     }
     
+    /// Get Cookie header value for a request being sent, marking the
+    /// cookies as used for eviction
+    pub fn cookie_header_for_request(&mut self, domain: &str, path: &str, is_secure: bool) -> Option<String> {
+        self.reindex();
+        let header = self.get_cookie_header(domain, path, is_secure)?;
+        let now = self.stamp();
+        if let Some(cookies) = self.cookies.get_mut(&domain_key(domain)) {
+            for cookie in cookies.iter_mut().filter(|c| c.matches(domain, path, is_secure)) {
+                cookie.last_access = now;
+            }
+        }
+        Some(header)
+    }
+    
+    /// Iterate over all cookies
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie> {
+        self.cookies.values().flatten()
+    }
+    
     /// Remove expired cookies
     pub fn cleanup(&mut self) {
         for cookies in self.cookies.values_mut() {
@@ -311,6 +491,14 @@ impl CookieJar {
         self.cookies.retain(|_, v| !v.is_empty());
     }
     
+    /// Remove session cookies, as when the browser exits
+    pub fn clear_session_cookies(&mut self) {
+        for cookies in self.cookies.values_mut() {
+            cookies.retain(|c| c.expires.is_some());
+        }
+        self.cookies.retain(|_, v| !v.is_empty());
+    }
+    
     /// Clear all cookies
     pub fn clear(&mut self) {
         self.cookies.clear();
@@ -318,7 +506,14 @@ impl CookieJar {
     
     /// Clear cookies for a specific domain
     pub fn clear_domain(&mut self, domain: &str) {
-        self.cookies.remove(domain);
+        self.reindex();
+        let key = domain_key(domain);
+        if let Some(cookies) = self.cookies.get_mut(&key) {
+            cookies.retain(|c| c.domain != domain);
+            if cookies.is_empty() {
+                self.cookies.remove(&key);
+            }
+        }
     }
     
     /// Get total cookie count
@@ -363,6 +558,11 @@ impl PartitionedCookieJar {
         }
     }
     
+    /// Add a stored partitioned cookie, keeping its timestamps
+    pub fn restore(&mut self, cookie: Cookie, partition_key: PartitionKey) {
+        self.partitioned.entry(partition_key).or_default().restore(cookie);
+    }
+    
     /// Add from Set-Cookie header with partition context
     pub fn add_from_header(
         &mut self,
@@ -370,7 +570,7 @@ impl PartitionedCookieJar {
         request_domain: &str,
         partition_key: Option<&PartitionKey>,
     ) {
-        if let Some(cookie) = parse_set_cookie(header, request_domain) {
+        if let Some(cookie) = parse_set_cookie_for_request(header, request_domain) {
             self.add(cookie, partition_key);
         }
     }
@@ -430,6 +630,20 @@ impl PartitionedCookieJar {
         self.partitioned.get(key)
     }
     
+    /// Iterate over partitions
+    pub fn partitions(&self) -> impl Iterator<Item = (&PartitionKey, &CookieJar)> {
+        self.partitioned.iter()
+    }
+    
+    /// Remove session cookies from all jars
+    pub fn clear_session_cookies(&mut self) {
+        self.unpartitioned.clear_session_cookies();
+        for jar in self.partitioned.values_mut() {
+            jar.clear_session_cookies();
+        }
+        self.partitioned.retain(|_, jar| !jar.is_empty());
+    }
+    
     /// Cleanup expired cookies in all jars
     pub fn cleanup(&mut self) {
         self.unpartitioned.cleanup();
@@ -589,4 +803,45 @@ mod tests {
         assert_eq!(cookies.len(), 1);
         assert_eq!(cookies[0].value, "B");
     }
+    
+    #[test]
+    fn test_cookie_eviction() {
+        let mut jar = CookieJar::with_limits(CookieLimits { per_domain: 2, total: 3 });
+        jar.add_from_header("a=1", "example.com");
+        jar.add_from_header("b=2", "www.example.com");
+        // Sending `a` makes `b` the least recently used
+        jar.cookie_header_for_request("example.com", "/", false);
+        jar.add_from_header("c=3", "example.com");
+        let names: Vec<_> = jar.get_cookies("example.com", "/", false).iter().map(|c| c.name.clone()).collect();
+        assert_eq!(names, ["a", "c"]);
+        
+        jar.add_from_header("d=4", "other.org");
+        jar.add_from_header("e=5", "other.org");
+        assert_eq!(jar.len(), 3);
+        assert!(jar.get_cookies("example.com", "/", false).iter().all(|c| c.name != "a"));
+    }
+    
+    #[test]
+    fn test_public_suffix_domain_rejected() {
+        let mut jar = CookieJar::new();
+        jar.add_from_header("a=1; Domain=co.uk", "shop.example.co.uk");
+        jar.add_from_header("b=2; Domain=other.com", "example.com");
+        assert!(jar.is_empty());
+        
+        jar.add_from_header("c=3; Domain=example.co.uk", "shop.example.co.uk");
+        assert_eq!(jar.get_cookies("www.example.co.uk", "/", false).len(), 1);
+        
+        let mut cookie = Cookie::new("s", "1", "api.example.co.uk", "/");
+        assert!(cookie.same_site_allows("api.example.co.uk", "www.example.co.uk", false));
+        assert!(!cookie.same_site_allows("api.example.co.uk", "other.co.uk", false));
+        assert!(cookie.same_site_allows("api.example.co.uk", "other.co.uk", true));
+        cookie.same_site = SameSite::Strict;
+        assert!(!cookie.same_site_allows("api.example.co.uk", "other.co.uk", true));
+    }
+    
+    #[test]
+    fn test_new_jar_is_current() {
+        assert!(CookieJar::new().index_is_current());
+        assert!(PartitionedCookieJar::new().unpartitioned().index_is_current());
+    }
 }
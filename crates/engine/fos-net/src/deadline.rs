@@ -0,0 +1,201 @@
+//! Request Deadlines
+//!
+//! A deadline bounds a whole request: DNS, connect, TLS, sending and
+//! receiving all share what is left of it, so a slow lookup leaves less
+//! time for the body rather than each phase getting its own timeout. A
+//! deadline may also carry a cancel token, which aborts the request from
+//! another thread at the next I/O wakeup.
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::NetError;
+
+/// Longest a blocked socket call goes without noticing cancellation
+pub const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Flag shared between a request and whoever may abort it
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Abort every request carrying this token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+    
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// When a request must be finished by, and how it can be cancelled
+#[derive(Debug, Clone, Default)]
+pub struct Deadline {
+    at: Option<Instant>,
+    token: Option<CancelToken>,
+}
+
+impl Deadline {
+    /// No time limit and no way to cancel
+    pub fn none() -> Self {
+        Self::default()
+    }
+    
+    /// Expire `timeout` from now
+    pub fn after(timeout: Duration) -> Self {
+        Self::at(Instant::now() + timeout)
+    }
+    
+    /// Expire at `at`
+    pub fn at(at: Instant) -> Self {
+        Self { at: Some(at), token: None }
+    }
+    
+    /// Also end when `token` is cancelled
+    pub fn with_token(mut self, token: CancelToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+    
+    /// When this expires, if it has a time limit
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.at
+    }
+    
+    /// Neither a time limit nor a cancel token
+    pub fn is_unbounded(&self) -> bool {
+        self.at.is_none() && self.token.is_none()
+    }
+    
+    pub fn is_cancelled(&self) -> bool {
+        self.token.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+    
+    pub fn is_expired(&self) -> bool {
+        self.at.is_some_and(|at| Instant::now() >= at)
+    }
+    
+    /// Time left, or `None` without a time limit
+    pub fn remaining(&self) -> Option<Duration> {
+        self.at.map(|at| at.saturating_duration_since(Instant::now()))
+    }
+    
+    /// `timeout`, cut short to the time left
+    pub fn limit(&self, timeout: Duration) -> Duration {
+        self.remaining().map_or(timeout, |left| left.min(timeout))
+    }
+    
+    /// Why the request must stop now, if it must
+    pub fn error(&self) -> Option<NetError> {
+        if self.is_cancelled() {
+            Some(NetError::Aborted)
+        } else if self.is_expired() {
+            Some(NetError::Timeout)
+        } else {
+            None
+        }
+    }
+    
+    /// Fail if the request was cancelled or ran out of time
+    pub fn check(&self) -> Result<(), NetError> {
+        self.error().map_or(Ok(()), Err)
+    }
+    
+    /// `check` for socket calls: cancellation is `ConnectionAborted` and
+    /// expiry `TimedOut`. Not `Interrupted`, which std's readers retry.
+    pub fn check_io(&self) -> io::Result<()> {
+        match self.error() {
+            Some(NetError::Aborted) => Err(io::Error::new(io::ErrorKind::ConnectionAborted, "request aborted")),
+            Some(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "request deadline exceeded")),
+            None => Ok(()),
+        }
+    }
+    
+    /// How long the next blocking call may wait: at most `timeout`, the
+    /// time left, and, when cancellable, the poll interval
+    pub fn next_wait(&self, timeout: Option<Duration>) -> Option<Duration> {
+        let poll = self.token.as_ref().map(|_| CANCEL_POLL_INTERVAL);
+        [timeout, self.remaining(), poll].into_iter().flatten().min()
+            // A zero timeout means "block forever" to the socket calls
+            .map(|wait| wait.max(Duration::from_millis(1)))
+    }
+}
+
+/// Resolve `host`, giving up when `deadline` ends. The lookup itself can't
+/// be interrupted, so it runs on a thread of its own that is left to
+/// finish in the background.
+pub fn resolve_within(host: &str, port: u16, deadline: &Deadline) -> io::Result<SocketAddr> {
+    let lookup = move |host: &str| (host, port).to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No address found for {}", host)));
+    if deadline.is_unbounded() {
+        return lookup(host);
+    }
+    
+    let (tx, rx) = mpsc::channel();
+    let owned = host.to_string();
+    thread::spawn(move || {
+        let _ = tx.send(lookup(&owned));
+    });
+    loop {
+        deadline.check_io()?;
+        let wait = deadline.next_wait(None).unwrap_or(CANCEL_POLL_INTERVAL);
+        match rx.recv_timeout(wait) {
+            Ok(result) => return result,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(io::Error::other("DNS lookup thread failed"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_deadline_budget() {
+        let unbounded = Deadline::none();
+        assert!(unbounded.is_unbounded());
+        assert_eq!(unbounded.limit(Duration::from_secs(30)), Duration::from_secs(30));
+        assert_eq!(unbounded.next_wait(None), None);
+        assert!(unbounded.check().is_ok());
+        
+        let deadline = Deadline::after(Duration::from_secs(5));
+        assert!(deadline.limit(Duration::from_secs(30)) <= Duration::from_secs(5));
+        assert!(deadline.limit(Duration::from_secs(1)) == Duration::from_secs(1));
+        
+        let token = CancelToken::new();
+        let cancellable = Deadline::none().with_token(token.clone());
+        assert_eq!(cancellable.next_wait(Some(Duration::from_secs(60))), Some(CANCEL_POLL_INTERVAL));
+        token.cancel();
+        assert!(matches!(cancellable.check(), Err(NetError::Aborted)));
+        assert_eq!(cancellable.check_io().unwrap_err().kind(), io::ErrorKind::ConnectionAborted);
+        
+        let expired = Deadline::at(Instant::now());
+        assert!(matches!(expired.check(), Err(NetError::Timeout)));
+        assert_eq!(expired.next_wait(None), Some(Duration::from_millis(1)));
+    }
+    
+    #[test]
+    fn test_resolve_within() {
+        let deadline = Deadline::after(Duration::from_secs(10));
+        assert!(resolve_within("127.0.0.1", 80, &deadline).is_ok());
+        
+        let token = CancelToken::new();
+        token.cancel();
+        let cancelled = Deadline::none().with_token(token);
+        assert_eq!(resolve_within("127.0.0.1", 80, &cancelled).unwrap_err().kind(), io::ErrorKind::ConnectionAborted);
+    }
+}
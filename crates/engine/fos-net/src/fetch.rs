@@ -3,6 +3,8 @@
 //! JavaScript-style fetch() implementation.
 
 use crate::{Response, NetError, ResourceLoader, loader::Request, loader::Method};
+use crate::upload::Upload;
+use crate::deadline::Deadline;
 
 /// Fetch options
 #[derive(Debug, Default)]
@@ -10,6 +12,11 @@ pub struct FetchOptions {
     pub method: String,
     pub headers: Vec<(String, String)>,
     pub body: Option<String>,
+    /// Body streamed from a blob or file, reporting upload progress
+    pub upload: Option<Upload>,
+    /// Ends the request when it passes or its token is cancelled, as an
+    /// `AbortSignal` would
+    pub deadline: Deadline,
 }
 
 impl FetchOptions {
@@ -31,6 +38,16 @@ impl FetchOptions {
         self.body = Some(body.to_string());
         self
     }
+    
+    pub fn upload(mut self, upload: Upload) -> Self {
+        self.upload = Some(upload);
+        self
+    }
+    
+    pub fn deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
 }
 
 /// Fetch a URL (async)
@@ -57,6 +74,9 @@ pub async fn fetch_with_options(url: &str, options: FetchOptions) -> Result<Fetc
         url: url.to_string(),
         headers: options.headers.into_iter().collect(),
         body: options.body.map(|b| b.into_bytes()),
+        upload: options.upload,
+        deadline: options.deadline,
+        ..Default::default()
     };
     
     let response = loader.request(req).await?;
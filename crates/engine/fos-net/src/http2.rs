@@ -760,6 +760,13 @@ impl Http2Connection {
         Ok(stream_id)
     }
     
+    /// Bytes `stream_id` may send now under the connection and stream
+    /// flow control windows
+    pub fn send_capacity(&self, stream_id: u32) -> usize {
+        let stream_window = self.streams.get(&stream_id).map_or(0, |s| s.send_window);
+        self.send_window.min(stream_window).max(0) as usize
+    }
+    
     /// Send data on a stream
     pub fn send_data<W: Write>(
         &mut self,
@@ -771,6 +778,11 @@ impl Http2Connection {
         // Check flow control
         let max_frame_size = self.remote_settings.max_frame_size as usize;
         
+        // Ending a stream with no data left takes an empty frame
+        if data.is_empty() && end_stream {
+            Frame::data(stream_id, Vec::new(), true).write_to(writer)?;
+        }
+        
         // Split data into frames if needed
         for chunk in data.chunks(max_frame_size) {
             let is_last = chunk.as_ptr() as usize + chunk.len() 
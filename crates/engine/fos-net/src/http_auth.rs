@@ -0,0 +1,433 @@
+//! HTTP Authentication
+//!
+//! Challenges from `WWW-Authenticate` (401) and `Proxy-Authenticate` (407)
+//! and the Basic (RFC 7617) and Digest (RFC 7616) credentials answering
+//! them. Credentials come from a [`CredentialProvider`], usually a prompt,
+//! and are cached per origin so later requests send them up front.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Authentication scheme of a challenge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuthScheme {
+    Basic,
+    Digest,
+}
+
+impl AuthScheme {
+    fn parse(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("basic") {
+            Some(Self::Basic)
+        } else if name.eq_ignore_ascii_case("digest") {
+            Some(Self::Digest)
+        } else {
+            None
+        }
+    }
+}
+
+/// Who asked for credentials: the origin server or a proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuthTarget {
+    Server,
+    Proxy,
+}
+
+impl AuthTarget {
+    /// Target of a 401 or 407 response
+    pub fn for_status(status: u16) -> Option<Self> {
+        match status {
+            401 => Some(Self::Server),
+            407 => Some(Self::Proxy),
+            _ => None,
+        }
+    }
+    
+    /// Response header carrying challenges
+    pub fn challenge_header(&self) -> &'static str {
+        match self {
+            Self::Server => "WWW-Authenticate",
+            Self::Proxy => "Proxy-Authenticate",
+        }
+    }
+    
+    /// Request header carrying credentials
+    pub fn authorization_header(&self) -> &'static str {
+        match self {
+            Self::Server => "Authorization",
+            Self::Proxy => "Proxy-Authorization",
+        }
+    }
+}
+
+/// One challenge of an authenticate header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    pub scheme: AuthScheme,
+    pub realm: String,
+    params: HashMap<String, String>,
+}
+
+impl Challenge {
+    /// Supported challenges in a header value; one header may hold several,
+    /// e.g. `Digest realm="a", nonce="x", Basic realm="a"`
+    pub fn parse_all(header: &str) -> Vec<Self> {
+        let mut challenges = Vec::new();
+        let mut current: Option<(&str, HashMap<String, String>)> = None;
+        let mut rest = header;
+        
+        loop {
+            rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+            if rest.is_empty() {
+                break;
+            }
+            let end = rest.find(|c: char| c == '=' || c == ',' || c.is_whitespace()).unwrap_or(rest.len());
+            let token = &rest[..end];
+            let after = rest[end..].trim_start();
+            
+            match after.strip_prefix('=') {
+                Some(value) => {
+                    let value = value.trim_start();
+                    let (value, remaining) = match value.strip_prefix('"') {
+                        Some(quoted) => parse_quoted(quoted),
+                        None => {
+                            let end = value.find(|c: char| c == ',' || c.is_whitespace()).unwrap_or(value.len());
+                            (value[..end].to_string(), &value[end..])
+                        }
+                    };
+                    if let Some((_, params)) = &mut current {
+                        params.insert(token.to_ascii_lowercase(), value);
+                    }
+                    rest = remaining;
+                }
+                None => {
+                    challenges.extend(current.take());
+                    current = Some((token, HashMap::new()));
+                    rest = after;
+                }
+            }
+        }
+        challenges.extend(current);
+        
+        challenges.into_iter()
+            .filter_map(|(scheme, mut params)| {
+                let scheme = AuthScheme::parse(scheme)?;
+                // Digest can't be answered without a nonce
+                if scheme == AuthScheme::Digest && !params.contains_key("nonce") {
+                    return None;
+                }
+                let realm = params.remove("realm").unwrap_or_default();
+                Some(Self { scheme, realm, params })
+            })
+            .collect()
+    }
+    
+    /// A parameter other than the realm, by lowercase name
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+    
+    /// The server only rejected an expired nonce, not the credentials
+    pub fn is_stale(&self) -> bool {
+        self.param("stale").is_some_and(|s| s.eq_ignore_ascii_case("true"))
+    }
+    
+    /// Header value answering this challenge. `nonce_count` counts the
+    /// requests made with the challenge's nonce, starting at 1.
+    pub fn authorization(&self, credentials: &Credentials, method: &str, uri: &str, nonce_count: u32) -> String {
+        match self.scheme {
+            AuthScheme::Basic => {
+                let pair = format!("{}:{}", credentials.username, credentials.password);
+                format!("Basic {}", base64_encode(pair.as_bytes()))
+            }
+            AuthScheme::Digest => {
+                let mut cnonce = [0u8; 16];
+                let _ = SystemRandom::new().fill(&mut cnonce);
+                self.digest_authorization(credentials, method, uri, nonce_count, &hex(&cnonce))
+            }
+        }
+    }
+    
+    fn digest_authorization(&self, credentials: &Credentials, method: &str, uri: &str, nonce_count: u32, cnonce: &str) -> String {
+        let algorithm = self.param("algorithm").unwrap_or("MD5");
+        let hash = |data: &str| {
+            if algorithm.to_ascii_uppercase().starts_with("SHA-256") {
+                hex(digest(&SHA256, data.as_bytes()).as_ref())
+            } else {
+                hex(&md5(data.as_bytes()))
+            }
+        };
+        let nonce = self.param("nonce").unwrap_or_default();
+        let qop = self.param("qop")
+            .is_some_and(|q| q.split(',').any(|v| v.trim().eq_ignore_ascii_case("auth")))
+            .then_some("auth");
+        let nc = format!("{:08x}", nonce_count);
+        
+        let mut ha1 = hash(&format!("{}:{}:{}", credentials.username, self.realm, credentials.password));
+        if algorithm.to_ascii_lowercase().ends_with("-sess") {
+            ha1 = hash(&format!("{}:{}:{}", ha1, nonce, cnonce));
+        }
+        let ha2 = hash(&format!("{}:{}", method, uri));
+        let response = match qop {
+            Some(qop) => hash(&format!("{}:{}:{}:{}:{}:{}", ha1, nonce, nc, cnonce, qop, ha2)),
+            // RFC 2069 servers that don't offer qop
+            None => hash(&format!("{}:{}:{}", ha1, nonce, ha2)),
+        };
+        
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+            quote(&credentials.username), quote(&self.realm), quote(nonce), quote(uri), algorithm, response,
+        );
+        if let Some(qop) = qop {
+            header.push_str(&format!(", qop={}, nc={}, cnonce=\"{}\"", qop, nc, cnonce));
+        }
+        if let Some(opaque) = self.param("opaque") {
+            header.push_str(&format!(", opaque=\"{}\"", quote(opaque)));
+        }
+        header
+    }
+}
+
+/// Quoted-string contents up to the closing quote, and what follows it
+fn parse_quoted(s: &str) -> (String, &str) {
+    let mut value = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (value, &s[i + 1..]),
+            '\\' => value.extend(chars.next().map(|(_, c)| c)),
+            c => value.push(c),
+        }
+    }
+    (value, "")
+}
+
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// A user name and password
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl Credentials {
+    pub fn new(username: &str, password: &str) -> Self {
+        Self { username: username.to_string(), password: password.to_string() }
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials").field("username", &self.username).finish_non_exhaustive()
+    }
+}
+
+/// What a credential prompt shows the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthPrompt {
+    pub target: AuthTarget,
+    /// Origin of the server, or `host:port` of the proxy
+    pub origin: String,
+    pub realm: String,
+    pub scheme: AuthScheme,
+    /// Credentials sent before were rejected
+    pub retry: bool,
+}
+
+/// Source of credentials for challenges, typically a browser prompt
+pub trait CredentialProvider: Send + Sync {
+    /// Credentials to answer the challenge with, or `None` to cancel
+    fn credentials(&self, prompt: &AuthPrompt) -> Option<Credentials>;
+}
+
+/// Credentials accepted for an origin, with the challenge they answer
+#[derive(Debug, Clone)]
+struct AuthEntry {
+    challenge: Challenge,
+    credentials: Credentials,
+    nonce_count: u32,
+}
+
+/// Credentials by origin, sent before being challenged again. Clones share
+/// the cache.
+#[derive(Debug, Clone, Default)]
+pub struct AuthCache {
+    entries: Arc<Mutex<HashMap<(AuthTarget, String), AuthEntry>>>,
+}
+
+impl AuthCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Remember credentials for the origin that issued `challenge`
+    pub fn insert(&self, target: AuthTarget, origin: &str, challenge: Challenge, credentials: Credentials) {
+        let entry = AuthEntry { challenge, credentials, nonce_count: 0 };
+        self.entries.lock().unwrap().insert((target, origin.to_string()), entry);
+    }
+    
+    /// Answer a new challenge with the cached credentials, e.g. when a
+    /// Digest nonce went stale. `false` if none are cached.
+    pub fn update_challenge(&self, target: AuthTarget, origin: &str, challenge: Challenge) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.get_mut(&(target, origin.to_string())) else { return false };
+        entry.challenge = challenge;
+        entry.nonce_count = 0;
+        true
+    }
+    
+    /// Cached credentials for an origin
+    pub fn credentials(&self, target: AuthTarget, origin: &str) -> Option<Credentials> {
+        self.entries.lock().unwrap().get(&(target, origin.to_string())).map(|e| e.credentials.clone())
+    }
+    
+    /// Authorization header value for a request to a cached origin
+    pub fn authorization(&self, target: AuthTarget, origin: &str, method: &str, uri: &str) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get_mut(&(target, origin.to_string()))?;
+        entry.nonce_count += 1;
+        Some(entry.challenge.authorization(&entry.credentials, method, uri, entry.nonce_count))
+    }
+    
+    /// Forget the credentials for an origin
+    pub fn remove(&self, target: AuthTarget, origin: &str) {
+        self.entries.lock().unwrap().remove(&(target, origin.to_string()));
+    }
+    
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn base64_encode(data: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::new();
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (chunk.get(1).copied().unwrap_or(0) as u32) << 8
+            | chunk.get(2).copied().unwrap_or(0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(CHARS[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+/// MD5, still the default Digest algorithm
+fn md5(data: &[u8]) -> [u8; 16] {
+    const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+    let k: Vec<u32> = (0..64).map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32).collect();
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+    
+    for chunk in msg.chunks(64) {
+        let m: Vec<u32> = chunk.chunks(4).map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]])).collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a).wrapping_add(k[i]).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(SHIFTS[i / 16 * 4 + i % 4]));
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+    
+    let mut out = [0u8; 16];
+    for (i, word) in state.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_challenges() {
+        let challenges = Challenge::parse_all(
+            r#"Negotiate, Digest realm="http-auth@example.org", qop="auth, auth-int", algorithm=SHA-256, nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS", Basic realm="say \"hi\"""#
+        );
+        assert_eq!(challenges.len(), 2);
+        assert_eq!(challenges[0].scheme, AuthScheme::Digest);
+        assert_eq!(challenges[0].realm, "http-auth@example.org");
+        assert_eq!(challenges[0].param("qop"), Some("auth, auth-int"));
+        assert_eq!(challenges[0].param("algorithm"), Some("SHA-256"));
+        assert_eq!(challenges[1].scheme, AuthScheme::Basic);
+        assert_eq!(challenges[1].realm, r#"say "hi""#);
+    }
+    
+    #[test]
+    fn test_basic_and_digest_responses() {
+        let basic = &Challenge::parse_all("Basic realm=\"WallyWorld\"")[0];
+        assert_eq!(
+            basic.authorization(&Credentials::new("Aladdin", "open sesame"), "GET", "/", 1),
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+        
+        // RFC 7616 section 3.9.1
+        let credentials = Credentials::new("Mufasa", "Circle of Life");
+        let cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+        for (algorithm, expected) in [
+            ("MD5", "8ca523f5e9506fed4657c9700eebdbec"),
+            ("SHA-256", "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1"),
+        ] {
+            let challenge = &Challenge::parse_all(&format!(
+                "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm={}, \
+                 nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"",
+                algorithm
+            ))[0];
+            let header = challenge.digest_authorization(&credentials, "GET", "/dir/index.html", 1, cnonce);
+            assert!(header.contains(&format!("response=\"{}\"", expected)), "{}", header);
+            assert!(header.contains("qop=auth, nc=00000001"));
+            assert!(header.contains("opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""));
+        }
+    }
+    
+    #[test]
+    fn test_auth_cache() {
+        let cache = AuthCache::new();
+        let challenge = Challenge::parse_all("Digest realm=\"r\", nonce=\"n\", qop=\"auth\"").remove(0);
+        cache.insert(AuthTarget::Server, "https://example.com", challenge, Credentials::new("u", "p"));
+        
+        let shared = cache.clone();
+        let first = shared.authorization(AuthTarget::Server, "https://example.com", "GET", "/").unwrap();
+        let second = cache.authorization(AuthTarget::Server, "https://example.com", "GET", "/").unwrap();
+        assert!(first.contains("nc=00000001") && second.contains("nc=00000002"));
+        assert!(cache.authorization(AuthTarget::Proxy, "https://example.com", "GET", "/").is_none());
+        
+        cache.remove(AuthTarget::Server, "https://example.com");
+        assert!(shared.credentials(AuthTarget::Server, "https://example.com").is_none());
+    }
+}
@@ -1,6 +1,6 @@
 //! fOS Networking
 //!
-//! HTTP client, WebSocket, SSE, and resource loading.
+//! HTTP client, WebSocket, SSE, report delivery, and resource loading.
 
 pub mod loader;
 pub mod fetch;
@@ -8,6 +8,7 @@ pub mod cache;
 pub mod websocket;
 pub mod sse;
 pub mod beacon;
+pub mod reporting;
 pub mod http2;
 pub mod xhr;
 pub mod http3;
@@ -16,13 +17,20 @@ pub mod network_opt;
 pub mod connection_pool;
 pub mod tcp;
 pub mod tls;
+pub mod client_cert;
 pub mod http1;
 pub mod cookies;
+pub mod cookie_store;
 pub mod client;
+pub mod http_auth;
+#[cfg(feature = "integrated-auth")]
+pub mod negotiate;
 pub mod coalescing;
 pub mod prefetch;
 pub mod cors;
 pub mod streaming;
+pub mod upload;
+pub mod deadline;
 pub mod priority;
 pub mod brotli_dict;
 pub mod request_dedup;
@@ -38,12 +46,14 @@ pub mod tiered_pool;
 pub mod request_fusion;
 pub mod delta_encoding;
 pub mod zero_copy;
+pub mod timing;
 
-pub use loader::{ResourceLoader, Request, Method};
+pub use loader::{ResourceLoader, Request, Method, Interception, RequestInterceptor, InterceptorChain};
 pub use fetch::{fetch, fetch_with_options, FetchOptions, FetchResponse};
 pub use websocket::{WebSocket, WebSocketState, WebSocketError, MessageData};
 pub use sse::{EventSource, EventSourceState, SseEvent};
 pub use beacon::{send_beacon, BeaconData};
+pub use reporting::{ReportingEndpoint, EndpointGroup, QueuedReport, ReportValue, ReportTransport, ReportUploader, DeliveryOutcome, parse_reporting_endpoints, parse_report_to};
 pub use http2::{Http2Connection, Stream as Http2Stream, Settings as Http2Settings, Frame as Http2Frame, Http2Error, Http2Event, HpackEncoder, HpackDecoder};
 pub use xhr::{XmlHttpRequest, ReadyState, ResponseType, XhrError, FormData, FormDataValue};
 pub use http3::{QuicConnection as Http3QuicConnection, Http3Connection, QuicError};
@@ -55,15 +65,23 @@ pub use quic::{
     QuicStream, StreamState, FlowController, CubicController, LossDetection,
     QpackEncoder, QpackDecoder,
 };
-pub use network_opt::{RequestCoalescer, PredictiveDns, DeltaSync, CrossTabCache};
+pub use network_opt::{RequestCoalescer, PredictiveDns, DeltaSync, CrossTabCache, CrossTabEntry, CrossTabLookup, CrossTabLoad};
 pub use connection_pool::{ConnectionPool, PooledConnection, PoolConfig, HostKey, AcquireResult};
 pub use client::{HttpClient, HttpClientBuilder, ClientConfig};
-pub use cookies::{Cookie, CookieJar, SameSite, PartitionKey, PartitionedCookieJar};
+pub use http_auth::{AuthCache, AuthPrompt, AuthScheme, AuthTarget, Challenge, CredentialProvider, Credentials};
+#[cfg(feature = "integrated-auth")]
+pub use negotiate::{AuthBackend, IntegratedAuth, IntegratedAuthError, IntegratedAuthPolicy, IntegratedScheme, SecurityContext};
+pub use cookies::{Cookie, CookieJar, CookieLimits, SameSite, PartitionKey, PartitionedCookieJar};
+pub use cookie_store::CookieStore;
+pub use fos_dom::url::{PublicSuffixList, Site};
 pub use tcp::{TcpConnection, TcpConfig, BufferedTcpConnection};
-pub use tls::{TlsStream, TlsConfig, TlsState};
+pub use tls::{TlsStream, TlsConfig, TlsState, TlsError};
+pub use client_cert::{ClientIdentity, ClientCertError, ClientCertManager, CertificateStore, PemDirectoryStore, CertificateRequest, CertificateSelector, AutoSelectPolicy, AutoSelectRule};
 pub use http1::{Http1Request, Http1Response, Http1Parser, HttpVersion};
 pub use cors::{CorsHandler, CorsCheck, CorsMode, CredentialsMode, CorsError, PreflightRequest, PreflightResponse, Origin as CorsOrigin};
 pub use streaming::{StreamingBody, StreamIterator, StreamState as StreamingState, TransferEncoding, ProgressBody, detect_encoding};
+pub use upload::{Upload, UploadBody, UploadSource, SharedBytes, FileSource, UploadControl, UploadError, UploadEventKind, UploadProgress, UploadListener};
+pub use deadline::{Deadline, CancelToken};
 pub use priority::{RequestPriority, PriorityQueue, PrioritizedRequest, ResourceType, BandwidthHints, QueueStats};
 pub use brotli_dict::{BrotliSharedDict, DictId, DictionaryBuilder, BrotliDecompressor, DictCache};
 pub use request_dedup::{RequestDeduplicator, RequestKey, DeduplicatedResponse, DeduplicationStats, SimpleDeduplicator};
@@ -80,6 +98,7 @@ pub use tiered_pool::{TieredConnectionPool, ConnectionKey, PooledConnection as T
 pub use request_fusion::{RequestFusion, FusionRequest, FusedBatch, FusionStats};
 pub use delta_encoding::{DeltaEncoder, DeltaDecoder, DeltaPatch, DeltaOp};
 pub use zero_copy::{MmapBuffer, ScatterGatherWriter, ZeroCopyPipeline, IoVec};
+pub use timing::{RequestTimings, TimedReader};
 pub use quic::{Bbrv2Controller, BbrState, CongestionAlgorithm, SessionCache, SessionTicket, SessionCacheKey, EarlyDataBuffer};
 
 
@@ -115,6 +134,18 @@ pub enum NetError {
     
     #[error("Invalid URL: {0}")]
     InvalidUrl(String),
+    
+    #[error("Request blocked: {0}")]
+    Blocked(String),
+    
+    #[error("TLS error: {0}")]
+    Tls(TlsError),
+    
+    #[error("Request timed out")]
+    Timeout,
+    
+    #[error("Request aborted")]
+    Aborted,
 }
 
 #[cfg(test)]
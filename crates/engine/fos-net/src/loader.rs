@@ -1,10 +1,19 @@
 //! Resource Loader
 //!
-//! HTTP client for network requests using custom HTTP client.
+//! HTTP client for network requests using custom HTTP client. Requests
+//! pass through an interception pipeline first, where embedders such as
+//! content blockers can block, redirect or rewrite them.
 
 use crate::{Response, NetError};
 use crate::client::{HttpClient, HttpClientBuilder};
+use crate::priority::ResourceType;
+use crate::upload::Upload;
+use crate::deadline::Deadline;
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Redirects interceptors may chain before a request fails
+const MAX_INTERCEPT_REDIRECTS: usize = 10;
 
 /// HTTP method
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -25,7 +34,7 @@ impl Default for Method {
 }
 
 impl Method {
-    fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             Method::Get => "GET",
             Method::Post => "POST",
@@ -45,6 +54,13 @@ pub struct Request {
     pub url: String,
     pub headers: HashMap<String, String>,
     pub body: Option<Vec<u8>>,
+    /// Body streamed from its source instead of `body`
+    pub upload: Option<Upload>,
+    /// When the request gives up, and the token that aborts it
+    pub deadline: Deadline,
+    /// URL of the document or worker making the request
+    pub initiator: Option<String>,
+    pub resource_type: ResourceType,
 }
 
 impl Request {
@@ -74,15 +90,114 @@ impl Request {
         self
     }
     
+    /// Stream the body from an upload source, with progress and control
+    pub fn with_upload(mut self, upload: Upload) -> Self {
+        self.upload = Some(upload);
+        self
+    }
+    
+    /// Give up at `deadline`, or when its token is cancelled
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+    
     pub fn with_json(self, json: &str) -> Self {
         self.with_header("Content-Type", "application/json")
             .with_body(json.as_bytes().to_vec())
     }
+    
+    pub fn with_initiator(mut self, initiator: &str) -> Self {
+        self.initiator = Some(initiator.to_string());
+        self
+    }
+    
+    pub fn with_resource_type(mut self, resource_type: ResourceType) -> Self {
+        self.resource_type = resource_type;
+        self
+    }
+}
+
+/// What an interceptor does with a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Interception {
+    /// Pass it on, possibly with changed headers
+    Continue,
+    Block,
+    /// Restart the pipeline at another URL
+    Redirect(String),
+}
+
+/// A stage of the interception pipeline
+pub trait RequestInterceptor: Send + Sync {
+    /// Look at a request before it is sent
+    fn intercept_request(&self, request: &mut Request) -> Interception;
+    
+    /// Look at the headers of the response to a request that was let through
+    fn intercept_response(&self, _request: &Request, _headers: &mut Vec<(String, String)>) {}
+}
+
+/// Interceptors in the order they were added
+#[derive(Clone, Default)]
+pub struct InterceptorChain {
+    interceptors: Vec<Arc<dyn RequestInterceptor>>,
+}
+
+impl InterceptorChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn add(&mut self, interceptor: Arc<dyn RequestInterceptor>) {
+        self.interceptors.push(interceptor);
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.interceptors.is_empty()
+    }
+    
+    /// Run a request through every interceptor. A redirect starts the
+    /// chain again at the new URL; a block fails the request.
+    pub fn apply_request(&self, mut request: Request) -> Result<Request, NetError> {
+        let mut redirects = 0;
+        'restart: loop {
+            for interceptor in &self.interceptors {
+                match interceptor.intercept_request(&mut request) {
+                    Interception::Continue => {}
+                    Interception::Block => return Err(NetError::Blocked(request.url)),
+                    Interception::Redirect(url) => {
+                        redirects += 1;
+                        if redirects > MAX_INTERCEPT_REDIRECTS {
+                            return Err(NetError::Network(format!("too many redirects intercepting {}", url)));
+                        }
+                        tracing::debug!("Intercepted {} -> {}", request.url, url);
+                        request.url = url;
+                        continue 'restart;
+                    }
+                }
+            }
+            return Ok(request);
+        }
+    }
+    
+    /// Run response headers through every interceptor
+    pub fn apply_response(&self, request: &Request, headers: &mut Vec<(String, String)>) {
+        for interceptor in &self.interceptors {
+            interceptor.intercept_response(request, headers);
+        }
+    }
+}
+
+impl std::fmt::Debug for InterceptorChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InterceptorChain").field("len", &self.interceptors.len()).finish()
+    }
 }
 
 /// Load resources from network  
 pub struct ResourceLoader {
     client: HttpClient,
+    interceptors: InterceptorChain,
 }
 
 impl ResourceLoader {
@@ -91,7 +206,12 @@ impl ResourceLoader {
             .user_agent("fOS-Engine/0.1")
             .build();
         
-        Self { client }
+        Self { client, interceptors: InterceptorChain::new() }
+    }
+    
+    /// Add a stage to the interception pipeline
+    pub fn add_interceptor(&mut self, interceptor: Arc<dyn RequestInterceptor>) {
+        self.interceptors.add(interceptor);
     }
     
     /// Fetch a URL with GET
@@ -99,18 +219,27 @@ impl ResourceLoader {
         self.request(Request::get(url)).await
     }
     
-    /// Make an HTTP request
+    /// Make an HTTP request, after the interception pipeline
     pub async fn request(&mut self, req: Request) -> Result<Response, NetError> {
+        let mut req = self.interceptors.apply_request(req)?;
         tracing::info!("HTTP {:?} {}", req.method, req.url);
         
-        let headers: Vec<(String, String)> = req.headers.into_iter().collect();
+        let headers: Vec<(String, String)> = req.headers.iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
         
-        self.client.request(
-            req.method.as_str(),
-            &req.url,
-            Some(headers),
-            req.body,
-        )
+        self.client.set_deadline(req.deadline.clone());
+        let mut response = match req.upload.take() {
+            Some(upload) => self.client.upload(req.method.as_str(), &req.url, Some(headers), upload)?,
+            None => self.client.request(
+                req.method.as_str(),
+                &req.url,
+                Some(headers),
+                req.body.take(),
+            )?,
+        };
+        self.interceptors.apply_response(&req, &mut response.headers);
+        Ok(response)
     }
 }
 
@@ -143,6 +272,30 @@ mod tests {
         assert_eq!(req.headers.get("Content-Type").unwrap(), "application/json");
     }
     
+    #[test]
+    fn test_interceptor_chain() {
+        struct Rewrite;
+        impl RequestInterceptor for Rewrite {
+            fn intercept_request(&self, request: &mut Request) -> Interception {
+                match request.url.as_str() {
+                    "https://ads.example/" => Interception::Block,
+                    "http://example.com/" => Interception::Redirect("https://example.com/".into()),
+                    _ => {
+                        request.headers.insert("DNT".into(), "1".into());
+                        Interception::Continue
+                    }
+                }
+            }
+        }
+        
+        let mut chain = InterceptorChain::new();
+        chain.add(Arc::new(Rewrite));
+        let req = chain.apply_request(Request::get("http://example.com/")).unwrap();
+        assert_eq!(req.url, "https://example.com/");
+        assert_eq!(req.headers.get("DNT").unwrap(), "1");
+        assert!(matches!(chain.apply_request(Request::get("https://ads.example/")), Err(NetError::Blocked(_))));
+    }
+    
     #[test]
     fn test_method_as_str() {
         assert_eq!(Method::Get.as_str(), "GET");
@@ -0,0 +1,289 @@
+//! GSSAPI Backend
+//!
+//! MIT Kerberos or Heimdal GSSAPI, loaded when first needed so the browser
+//! runs on systems without it. Negotiate uses the SPNEGO mechanism and NTLM
+//! the gss-ntlmssp one, each with the default credentials of the logged-in
+//! user, e.g. from `kinit`.
+
+use std::ffi::{c_void, CStr, CString};
+use std::ptr;
+use std::sync::Arc;
+
+use super::{AuthBackend, IntegratedAuthError, IntegratedScheme, SecurityContext};
+
+/// Libraries tried in order
+const LIBRARIES: [&str; 4] = [
+    "libgssapi_krb5.so.2",
+    "libgssapi.so.3",
+    "libgssapi_krb5.dylib",
+    "/System/Library/Frameworks/GSS.framework/GSS",
+];
+
+/// GSS_C_NT_HOSTBASED_SERVICE, 1.2.840.113554.1.2.1.4
+const NT_HOSTBASED_SERVICE: &[u8] = b"\x2a\x86\x48\x86\xf7\x12\x01\x02\x01\x04";
+/// SPNEGO, 1.3.6.1.5.5.2
+const SPNEGO_MECHANISM: &[u8] = b"\x2b\x06\x01\x05\x05\x02";
+/// NTLMSSP, 1.3.6.1.4.1.311.2.2.10
+const NTLM_MECHANISM: &[u8] = b"\x2b\x06\x01\x04\x01\x82\x37\x02\x02\x0a";
+
+const GSS_S_COMPLETE: u32 = 0;
+const GSS_S_CONTINUE_NEEDED: u32 = 1;
+const GSS_C_MUTUAL_FLAG: u32 = 2;
+const GSS_C_SEQUENCE_FLAG: u32 = 8;
+
+#[repr(C)]
+struct OidDesc {
+    length: u32,
+    elements: *mut c_void,
+}
+
+#[repr(C)]
+struct BufferDesc {
+    length: usize,
+    value: *mut c_void,
+}
+
+impl BufferDesc {
+    const EMPTY: Self = Self { length: 0, value: ptr::null_mut() };
+}
+
+type Name = *mut c_void;
+type Context = *mut c_void;
+
+type ImportName = unsafe extern "C" fn(*mut u32, *mut BufferDesc, *mut OidDesc, *mut Name) -> u32;
+type InitSecContext = unsafe extern "C" fn(
+    *mut u32,
+    *mut c_void,
+    *mut Context,
+    Name,
+    *mut OidDesc,
+    u32,
+    u32,
+    *mut c_void,
+    *mut BufferDesc,
+    *mut *mut OidDesc,
+    *mut BufferDesc,
+    *mut u32,
+    *mut u32,
+) -> u32;
+type ReleaseBuffer = unsafe extern "C" fn(*mut u32, *mut BufferDesc) -> u32;
+type ReleaseName = unsafe extern "C" fn(*mut u32, *mut Name) -> u32;
+type DeleteSecContext = unsafe extern "C" fn(*mut u32, *mut Context, *mut BufferDesc) -> u32;
+type DisplayStatus = unsafe extern "C" fn(*mut u32, u32, i32, *mut OidDesc, *mut u32, *mut BufferDesc) -> u32;
+
+/// Functions of a loaded GSSAPI library
+struct Library {
+    import_name: ImportName,
+    init_sec_context: InitSecContext,
+    release_buffer: ReleaseBuffer,
+    release_name: ReleaseName,
+    delete_sec_context: DeleteSecContext,
+    display_status: DisplayStatus,
+}
+
+// The library is never unloaded and GSSAPI calls on separate contexts may
+// run on any thread
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+impl Library {
+    fn load() -> Result<Self, IntegratedAuthError> {
+        for name in LIBRARIES {
+            let path = CString::new(name).expect("library name has no NUL");
+            // SAFETY: dlopen with a valid C string; the handle is kept for
+            // the life of the process
+            let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+            if handle.is_null() {
+                continue;
+            }
+            let symbol = |name: &CStr| {
+                // SAFETY: handle is a library dlopen returned
+                let address = unsafe { libc::dlsym(handle, name.as_ptr()) };
+                (!address.is_null()).then_some(address)
+            };
+            let resolved = (|| {
+                // SAFETY: the symbols are the GSSAPI functions of RFC 2744,
+                // which the function types follow
+                unsafe {
+                    Some(Self {
+                        import_name: std::mem::transmute::<*mut c_void, ImportName>(symbol(c"gss_import_name")?),
+                        init_sec_context: std::mem::transmute::<*mut c_void, InitSecContext>(symbol(c"gss_init_sec_context")?),
+                        release_buffer: std::mem::transmute::<*mut c_void, ReleaseBuffer>(symbol(c"gss_release_buffer")?),
+                        release_name: std::mem::transmute::<*mut c_void, ReleaseName>(symbol(c"gss_release_name")?),
+                        delete_sec_context: std::mem::transmute::<*mut c_void, DeleteSecContext>(symbol(c"gss_delete_sec_context")?),
+                        display_status: std::mem::transmute::<*mut c_void, DisplayStatus>(symbol(c"gss_display_status")?),
+                    })
+                }
+            })();
+            match resolved {
+                Some(library) => {
+                    tracing::debug!("Loaded GSSAPI from {}", name);
+                    return Ok(library);
+                }
+                None => {
+                    tracing::debug!("{} lacks GSSAPI functions", name);
+                    // SAFETY: nothing from the library is in use
+                    unsafe { libc::dlclose(handle) };
+                }
+            }
+        }
+        Err(IntegratedAuthError::Unavailable("no GSSAPI library found".to_string()))
+    }
+    
+    /// Readable message for a failed call
+    fn error(&self, call: &str, major: u32, minor: u32) -> IntegratedAuthError {
+        let mut messages = Vec::new();
+        for (status, kind) in [(major, 1), (minor, 2)] {
+            let mut context = 0u32;
+            loop {
+                let mut discard = 0u32;
+                let mut buffer = BufferDesc::EMPTY;
+                // SAFETY: display_status writes the message to buffer,
+                // which is released after copying
+                let result = unsafe {
+                    (self.display_status)(&mut discard, status, kind, ptr::null_mut(), &mut context, &mut buffer)
+                };
+                if result != GSS_S_COMPLETE {
+                    break;
+                }
+                // SAFETY: buffer holds `length` bytes from the library
+                let text = unsafe { buffer_bytes(&buffer) };
+                messages.push(String::from_utf8_lossy(&text).trim_end_matches('\0').to_string());
+                // SAFETY: buffer was allocated by the library
+                unsafe { (self.release_buffer)(&mut discard, &mut buffer) };
+                if context == 0 {
+                    break;
+                }
+            }
+        }
+        IntegratedAuthError::Failed(format!("{}: {}", call, messages.join("; ")))
+    }
+}
+
+/// Bytes of a buffer the library filled
+unsafe fn buffer_bytes(buffer: &BufferDesc) -> Vec<u8> {
+    if buffer.value.is_null() || buffer.length == 0 {
+        return Vec::new();
+    }
+    // SAFETY: the caller passes a buffer of `length` bytes at `value`
+    unsafe { std::slice::from_raw_parts(buffer.value as *const u8, buffer.length).to_vec() }
+}
+
+/// Whether a major status is an error rather than complete or continue
+fn is_error(major: u32) -> bool {
+    major & 0xffff_0000 != 0
+}
+
+/// GSSAPI as the integrated authentication backend
+pub struct GssapiBackend {
+    library: Arc<Library>,
+}
+
+impl GssapiBackend {
+    /// Load the system's GSSAPI library
+    pub fn load() -> Result<Self, IntegratedAuthError> {
+        Ok(Self { library: Arc::new(Library::load()?) })
+    }
+}
+
+impl AuthBackend for GssapiBackend {
+    fn start(&self, scheme: IntegratedScheme, host: &str) -> Result<Box<dyn SecurityContext>, IntegratedAuthError> {
+        let service = format!("HTTP@{}", host);
+        let mut input = BufferDesc { length: service.len(), value: service.as_ptr() as *mut c_void };
+        let mut name_type = oid(NT_HOSTBASED_SERVICE);
+        let mut name: Name = ptr::null_mut();
+        let mut minor = 0u32;
+        // SAFETY: input and name_type point at live data for the call; the
+        // name is released when the context drops
+        let major = unsafe { (self.library.import_name)(&mut minor, &mut input, &mut name_type, &mut name) };
+        if is_error(major) {
+            return Err(self.library.error("gss_import_name", major, minor));
+        }
+        Ok(Box::new(GssContext {
+            library: self.library.clone(),
+            mechanism: match scheme {
+                IntegratedScheme::Negotiate => SPNEGO_MECHANISM,
+                IntegratedScheme::Ntlm => NTLM_MECHANISM,
+            },
+            target: name,
+            context: ptr::null_mut(),
+            complete: false,
+        }))
+    }
+}
+
+fn oid(elements: &'static [u8]) -> OidDesc {
+    OidDesc { length: elements.len() as u32, elements: elements.as_ptr() as *mut c_void }
+}
+
+/// A GSSAPI security context
+struct GssContext {
+    library: Arc<Library>,
+    mechanism: &'static [u8],
+    target: Name,
+    context: Context,
+    complete: bool,
+}
+
+// A context is used from one thread at a time, which GSSAPI allows
+unsafe impl Send for GssContext {}
+
+impl SecurityContext for GssContext {
+    fn step(&mut self, server_token: Option<&[u8]>) -> Result<Vec<u8>, IntegratedAuthError> {
+        if self.complete && server_token.is_none() {
+            return Err(IntegratedAuthError::Failed("handshake already complete".to_string()));
+        }
+        let mut input = match server_token {
+            Some(token) => BufferDesc { length: token.len(), value: token.as_ptr() as *mut c_void },
+            None => BufferDesc::EMPTY,
+        };
+        let mut mechanism = oid(self.mechanism);
+        let mut output = BufferDesc::EMPTY;
+        let mut minor = 0u32;
+        // SAFETY: every pointer refers to live data for the call; output is
+        // allocated by the library and released below
+        let major = unsafe {
+            (self.library.init_sec_context)(
+                &mut minor,
+                ptr::null_mut(),
+                &mut self.context,
+                self.target,
+                &mut mechanism,
+                GSS_C_MUTUAL_FLAG | GSS_C_SEQUENCE_FLAG,
+                0,
+                ptr::null_mut(),
+                &mut input,
+                ptr::null_mut(),
+                &mut output,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        // SAFETY: output holds `length` bytes from the library
+        let token = unsafe { buffer_bytes(&output) };
+        let mut discard = 0u32;
+        // SAFETY: output was allocated by the library
+        unsafe { (self.library.release_buffer)(&mut discard, &mut output) };
+        
+        if is_error(major) {
+            return Err(self.library.error("gss_init_sec_context", major, minor));
+        }
+        self.complete = major & 0xffff == GSS_S_COMPLETE;
+        debug_assert!(self.complete || major & 0xffff == GSS_S_CONTINUE_NEEDED);
+        Ok(token)
+    }
+}
+
+impl Drop for GssContext {
+    fn drop(&mut self) {
+        let mut minor = 0u32;
+        // SAFETY: the context and name came from the library and are not
+        // used again
+        unsafe {
+            if !self.context.is_null() {
+                (self.library.delete_sec_context)(&mut minor, &mut self.context, ptr::null_mut());
+            }
+            (self.library.release_name)(&mut minor, &mut self.target);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Integrated Authentication
+//!
+//! Negotiate (SPNEGO, usually Kerberos) and NTLM answered with the user's
+//! operating system login rather than a password prompt, through GSSAPI on
+//! Unix and SSPI on Windows. Both schemes authenticate a connection rather
+//! than a request: the handshake runs over one connection, which then stays
+//! with the login that opened it. Only servers the policy allows are sent
+//! these credentials.
+
+use std::fmt;
+use std::sync::Arc;
+
+#[cfg(unix)]
+mod gssapi;
+#[cfg(windows)]
+mod sspi;
+
+#[cfg(unix)]
+pub use gssapi::GssapiBackend;
+#[cfg(windows)]
+pub use sspi::SspiBackend;
+
+/// Handshake legs answered for one request before giving up
+pub const MAX_HANDSHAKE_LEGS: usize = 4;
+
+/// Connection-based authentication scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntegratedScheme {
+    Negotiate,
+    Ntlm,
+}
+
+impl IntegratedScheme {
+    pub fn parse(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("negotiate") {
+            Some(Self::Negotiate)
+        } else if name.eq_ignore_ascii_case("ntlm") {
+            Some(Self::Ntlm)
+        } else {
+            None
+        }
+    }
+    
+    /// Name in authenticate and authorization headers
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Negotiate => "Negotiate",
+            Self::Ntlm => "NTLM",
+        }
+    }
+    
+    /// Schemes offered in authenticate header values, most preferred
+    /// first, each with the token the server sent, if any
+    pub fn offered<'a>(headers: impl IntoIterator<Item = &'a str>) -> Vec<(Self, Option<Vec<u8>>)> {
+        let mut offered: Vec<(Self, Option<Vec<u8>>)> = headers.into_iter()
+            .flat_map(|value| value.split(','))
+            .filter_map(|challenge| {
+                let mut parts = challenge.split_whitespace();
+                let scheme = Self::parse(parts.next()?)?;
+                Some((scheme, parts.next().and_then(base64_decode)))
+            })
+            .collect();
+        offered.sort_by_key(|(scheme, _)| *scheme as u8);
+        offered.dedup_by_key(|(scheme, _)| *scheme);
+        offered
+    }
+}
+
+impl fmt::Display for IntegratedScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Failure of integrated authentication
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IntegratedAuthError {
+    /// No GSSAPI library or security package for the scheme
+    #[error("integrated authentication unavailable: {0}")]
+    Unavailable(String),
+    /// The security library rejected a step of the handshake
+    #[error("integrated authentication failed: {0}")]
+    Failed(String),
+}
+
+/// One handshake with a server
+pub trait SecurityContext: Send {
+    /// Token to send next, given the server's last token; `None` to start.
+    /// After the final leg a server may send a token for mutual
+    /// authentication, which is checked the same way.
+    fn step(&mut self, server_token: Option<&[u8]>) -> Result<Vec<u8>, IntegratedAuthError>;
+}
+
+/// Security library that runs handshakes with the user's login
+pub trait AuthBackend: Send + Sync {
+    /// Start a handshake with the server whose HTTP service is on `host`
+    /// (`host` or `host:port`)
+    fn start(&self, scheme: IntegratedScheme, host: &str) -> Result<Box<dyn SecurityContext>, IntegratedAuthError>;
+}
+
+/// The operating system's security library, if it can be loaded
+pub fn platform_backend() -> Option<Arc<dyn AuthBackend>> {
+    #[cfg(unix)]
+    {
+        match GssapiBackend::load() {
+            Ok(backend) => Some(Arc::new(backend)),
+            Err(e) => {
+                tracing::debug!("{}", e);
+                None
+            }
+        }
+    }
+    
+    #[cfg(windows)]
+    {
+        Some(Arc::new(SspiBackend))
+    }
+    
+    #[cfg(not(any(unix, windows)))]
+    {
+        None
+    }
+}
+
+/// Where integrated authentication may be used, as set by policy
+///
+/// Servers are listed as host names: `*` allows any, a leading `*` allows
+/// names ending in the rest (`*.corp.example`) and anything else allows
+/// that host only. Nothing is allowed until a list is set, so the login
+/// never goes to arbitrary sites.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegratedAuthPolicy {
+    allowed_servers: Vec<String>,
+    schemes: Vec<IntegratedScheme>,
+    /// Name the service by `host:port` for non-default ports
+    include_port_in_spn: bool,
+}
+
+impl Default for IntegratedAuthPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_servers: Vec::new(),
+            schemes: vec![IntegratedScheme::Negotiate, IntegratedScheme::Ntlm],
+            include_port_in_spn: false,
+        }
+    }
+}
+
+impl IntegratedAuthPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Servers allowed, as a comma-separated list
+    pub fn with_allowed_servers(mut self, list: &str) -> Self {
+        self.allowed_servers = list.split(',')
+            .map(|s| s.trim().to_ascii_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect();
+        self
+    }
+    
+    /// Schemes used; others offered by servers are ignored
+    pub fn with_schemes(mut self, schemes: &[IntegratedScheme]) -> Self {
+        self.schemes = schemes.to_vec();
+        self
+    }
+    
+    pub fn with_port_in_spn(mut self, include: bool) -> Self {
+        self.include_port_in_spn = include;
+        self
+    }
+    
+    /// Whether the login may be sent to `host`
+    pub fn allows_server(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.allowed_servers.iter().any(|pattern| match pattern.strip_prefix('*') {
+            Some(suffix) => host.ends_with(suffix),
+            None => host == *pattern,
+        })
+    }
+    
+    pub fn allows_scheme(&self, scheme: IntegratedScheme) -> bool {
+        self.schemes.contains(&scheme)
+    }
+    
+    /// Host part of the service principal name for a server
+    pub fn service_host(&self, host: &str, port: u16, default_port: u16) -> String {
+        if self.include_port_in_spn && port != default_port {
+            format!("{}:{}", host, port)
+        } else {
+            host.to_string()
+        }
+    }
+}
+
+/// Integrated authentication for a client: the policy and the security
+/// library answering challenges it allows
+#[derive(Clone)]
+pub struct IntegratedAuth {
+    pub policy: IntegratedAuthPolicy,
+    pub backend: Arc<dyn AuthBackend>,
+}
+
+impl IntegratedAuth {
+    pub fn new(policy: IntegratedAuthPolicy, backend: Arc<dyn AuthBackend>) -> Self {
+        Self { policy, backend }
+    }
+    
+    /// With the operating system's security library, if there is one
+    pub fn platform(policy: IntegratedAuthPolicy) -> Option<Self> {
+        platform_backend().map(|backend| Self::new(policy, backend))
+    }
+    
+    /// Scheme to answer a 401 from `host` with, and the server's token
+    pub fn choose<'a>(&self, host: &str, headers: impl IntoIterator<Item = &'a str>) -> Option<(IntegratedScheme, Option<Vec<u8>>)> {
+        if !self.policy.allows_server(host) {
+            return None;
+        }
+        IntegratedScheme::offered(headers).into_iter()
+            .find(|(scheme, _)| self.policy.allows_scheme(*scheme))
+    }
+}
+
+impl fmt::Debug for IntegratedAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntegratedAuth").field("policy", &self.policy).finish_non_exhaustive()
+    }
+}
+
+/// `Authorization` value carrying a token
+pub fn authorization(scheme: IntegratedScheme, token: &[u8]) -> String {
+    format!("{} {}", scheme, crate::http_auth::base64_encode(token))
+}
+
+fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let input = input.trim().trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for &c in input {
+        bits = bits << 6 | value(c)? as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Two-leg handshake standing in for a security library
+    struct Scripted;
+    
+    struct ScriptedContext(usize);
+    
+    impl SecurityContext for ScriptedContext {
+        fn step(&mut self, server_token: Option<&[u8]>) -> Result<Vec<u8>, IntegratedAuthError> {
+            self.0 += 1;
+            match (self.0, server_token) {
+                (1, None) => Ok(b"negotiate".to_vec()),
+                (2, Some(b"challenge")) => Ok(b"authenticate".to_vec()),
+                _ => Err(IntegratedAuthError::Failed("unexpected token".to_string())),
+            }
+        }
+    }
+    
+    impl AuthBackend for Scripted {
+        fn start(&self, _scheme: IntegratedScheme, _host: &str) -> Result<Box<dyn SecurityContext>, IntegratedAuthError> {
+            Ok(Box::new(ScriptedContext(0)))
+        }
+    }
+    
+    #[test]
+    fn test_policy_and_offered_schemes() {
+        let policy = IntegratedAuthPolicy::new().with_allowed_servers("*.corp.example, intranet");
+        assert!(policy.allows_server("wiki.corp.example"));
+        assert!(policy.allows_server("INTRANET"));
+        assert!(!policy.allows_server("intranet.evil.example"));
+        assert!(!IntegratedAuthPolicy::new().allows_server("intranet"));
+        assert_eq!(policy.service_host("intranet", 8080, 80), "intranet");
+        assert_eq!(policy.clone().with_port_in_spn(true).service_host("intranet", 8080, 80), "intranet:8080");
+        
+        let offered = IntegratedScheme::offered(["NTLM", "Basic realm=\"x\", Negotiate"]);
+        assert_eq!(offered, [(IntegratedScheme::Negotiate, None), (IntegratedScheme::Ntlm, None)]);
+        assert_eq!(IntegratedScheme::offered(["NTLM Y2hhbGxlbmdl"]), [(IntegratedScheme::Ntlm, Some(b"challenge".to_vec()))]);
+        
+        let auth = IntegratedAuth::new(policy.with_schemes(&[IntegratedScheme::Ntlm]), Arc::new(Scripted));
+        assert_eq!(auth.choose("intranet", ["Negotiate", "NTLM"]), Some((IntegratedScheme::Ntlm, None)));
+        assert_eq!(auth.choose("example.com", ["NTLM"]), None);
+        
+        let mut context = auth.backend.start(IntegratedScheme::Ntlm, "intranet").unwrap();
+        let first = context.step(None).unwrap();
+        assert_eq!(authorization(IntegratedScheme::Ntlm, &first), "NTLM bmVnb3RpYXRl");
+        assert_eq!(context.step(Some(b"challenge")).unwrap(), b"authenticate");
+    }
+}
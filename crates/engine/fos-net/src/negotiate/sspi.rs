@@ -0,0 +1,198 @@
+//! SSPI Backend
+//!
+//! Windows security packages "Negotiate" and "NTLM" with the credentials of
+//! the logged-in user.
+
+use std::ffi::c_void;
+use std::ptr;
+
+use super::{AuthBackend, IntegratedAuthError, IntegratedScheme, SecurityContext};
+
+const SECPKG_CRED_OUTBOUND: u32 = 2;
+const SECBUFFER_VERSION: u32 = 0;
+const SECBUFFER_TOKEN: u32 = 2;
+const SECURITY_NATIVE_DREP: u32 = 0x10;
+const ISC_REQ_MUTUAL_AUTH: u32 = 0x2;
+const ISC_REQ_ALLOCATE_MEMORY: u32 = 0x100;
+const SEC_E_OK: i32 = 0;
+const SEC_I_CONTINUE_NEEDED: i32 = 0x0009_0312;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SecHandle {
+    lower: usize,
+    upper: usize,
+}
+
+impl SecHandle {
+    const NULL: Self = Self { lower: 0, upper: 0 };
+    
+    fn is_null(&self) -> bool {
+        self.lower == 0 && self.upper == 0
+    }
+}
+
+#[repr(C)]
+struct SecBuffer {
+    length: u32,
+    buffer_type: u32,
+    buffer: *mut c_void,
+}
+
+#[repr(C)]
+struct SecBufferDesc {
+    version: u32,
+    count: u32,
+    buffers: *mut SecBuffer,
+}
+
+#[link(name = "secur32")]
+unsafe extern "system" {
+    fn AcquireCredentialsHandleW(
+        principal: *const u16,
+        package: *const u16,
+        credential_use: u32,
+        logon_id: *mut c_void,
+        auth_data: *mut c_void,
+        get_key_fn: *mut c_void,
+        get_key_argument: *mut c_void,
+        credential: *mut SecHandle,
+        expiry: *mut i64,
+    ) -> i32;
+    fn InitializeSecurityContextW(
+        credential: *mut SecHandle,
+        context: *mut SecHandle,
+        target_name: *const u16,
+        context_req: u32,
+        reserved1: u32,
+        target_data_rep: u32,
+        input: *mut SecBufferDesc,
+        reserved2: u32,
+        new_context: *mut SecHandle,
+        output: *mut SecBufferDesc,
+        context_attr: *mut u32,
+        expiry: *mut i64,
+    ) -> i32;
+    fn DeleteSecurityContext(context: *mut SecHandle) -> i32;
+    fn FreeCredentialsHandle(credential: *mut SecHandle) -> i32;
+    fn FreeContextBuffer(buffer: *mut c_void) -> i32;
+}
+
+/// NUL-terminated UTF-16
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(Some(0)).collect()
+}
+
+/// SSPI as the integrated authentication backend
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SspiBackend;
+
+impl AuthBackend for SspiBackend {
+    fn start(&self, scheme: IntegratedScheme, host: &str) -> Result<Box<dyn SecurityContext>, IntegratedAuthError> {
+        let package = wide(scheme.as_str());
+        let mut credential = SecHandle::NULL;
+        let mut expiry = 0i64;
+        // SAFETY: package is a live NUL-terminated string; the handle is
+        // freed when the context drops
+        let status = unsafe {
+            AcquireCredentialsHandleW(
+                ptr::null(),
+                package.as_ptr(),
+                SECPKG_CRED_OUTBOUND,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &mut credential,
+                &mut expiry,
+            )
+        };
+        if status != SEC_E_OK {
+            return Err(IntegratedAuthError::Unavailable(format!("AcquireCredentialsHandle for {}: {:#x}", scheme, status)));
+        }
+        Ok(Box::new(SspiContext {
+            credential,
+            context: SecHandle::NULL,
+            target: wide(&format!("HTTP/{}", host)),
+            complete: false,
+        }))
+    }
+}
+
+/// An SSPI security context
+struct SspiContext {
+    credential: SecHandle,
+    context: SecHandle,
+    target: Vec<u16>,
+    complete: bool,
+}
+
+// Handles are used from one thread at a time, which SSPI allows
+unsafe impl Send for SspiContext {}
+
+impl SecurityContext for SspiContext {
+    fn step(&mut self, server_token: Option<&[u8]>) -> Result<Vec<u8>, IntegratedAuthError> {
+        if self.complete && server_token.is_none() {
+            return Err(IntegratedAuthError::Failed("handshake already complete".to_string()));
+        }
+        let mut input_buffer = SecBuffer {
+            length: server_token.map_or(0, |t| t.len() as u32),
+            buffer_type: SECBUFFER_TOKEN,
+            buffer: server_token.map_or(ptr::null_mut(), |t| t.as_ptr() as *mut c_void),
+        };
+        let mut input = SecBufferDesc { version: SECBUFFER_VERSION, count: 1, buffers: &mut input_buffer };
+        let mut output_buffer = SecBuffer { length: 0, buffer_type: SECBUFFER_TOKEN, buffer: ptr::null_mut() };
+        let mut output = SecBufferDesc { version: SECBUFFER_VERSION, count: 1, buffers: &mut output_buffer };
+        let mut attributes = 0u32;
+        let mut expiry = 0i64;
+        let first = self.context.is_null();
+        // SAFETY: every pointer refers to live data for the call; the output
+        // token is allocated by SSPI and freed below
+        let status = unsafe {
+            InitializeSecurityContextW(
+                &mut self.credential,
+                if first { ptr::null_mut() } else { &mut self.context },
+                self.target.as_ptr(),
+                ISC_REQ_MUTUAL_AUTH | ISC_REQ_ALLOCATE_MEMORY,
+                0,
+                SECURITY_NATIVE_DREP,
+                if server_token.is_some() { &mut input } else { ptr::null_mut() },
+                0,
+                &mut self.context,
+                &mut output,
+                &mut attributes,
+                &mut expiry,
+            )
+        };
+        let token = if output_buffer.buffer.is_null() {
+            Vec::new()
+        } else {
+            // SAFETY: SSPI allocated `length` bytes at `buffer`
+            let token = unsafe {
+                std::slice::from_raw_parts(output_buffer.buffer as *const u8, output_buffer.length as usize).to_vec()
+            };
+            // SAFETY: the buffer came from SSPI and is not used again
+            unsafe { FreeContextBuffer(output_buffer.buffer) };
+            token
+        };
+        
+        match status {
+            SEC_E_OK => self.complete = true,
+            SEC_I_CONTINUE_NEEDED => {}
+            status => return Err(IntegratedAuthError::Failed(format!("InitializeSecurityContext: {:#x}", status))),
+        }
+        Ok(token)
+    }
+}
+
+impl Drop for SspiContext {
+    fn drop(&mut self) {
+        // SAFETY: the handles came from SSPI and are not used again
+        unsafe {
+            if !self.context.is_null() {
+                DeleteSecurityContext(&mut self.context);
+            }
+            FreeCredentialsHandle(&mut self.credential);
+        }
+    }
+}
@@ -2,9 +2,12 @@
 //!
 //! Request coalescing, predictive DNS, global connection pool, delta sync.
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, mpsc};
 use std::time::{Duration, Instant};
 
+use crate::cache::{CacheStats, CacheValidators};
+
 /// Request coalescing - batches small requests
 #[derive(Debug, Default)]
 pub struct RequestCoalescer {
@@ -212,13 +215,71 @@ impl DeltaSync {
     }
 }
 
-/// Cross-tab resource sharing
-#[derive(Debug, Default)]
+/// How long a tab waits on another tab loading the same URL before
+/// making its own request
+pub const SINGLE_FLIGHT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default byte cap of the cross-tab index
+pub const CROSS_TAB_CAPACITY: usize = 25 * 1024 * 1024;
+
+/// Cross-tab resource sharing: one index for the browser process, handed
+/// to every tab as a cloned handle. Resources are stored once by content,
+/// validators are kept per URL, a URL is loaded or revalidated by one tab
+/// at a time while the others wait for its answer, and subscribers hear
+/// about URLs whose content changed. Past its byte cap, the least recently
+/// used resources are dropped along with every URL indexed to them.
+#[derive(Debug, Clone)]
 pub struct CrossTabCache {
+    inner: Arc<CrossTabShared>,
+}
+
+#[derive(Debug, Default)]
+struct CrossTabShared {
+    state: Mutex<CrossTabState>,
+    /// Signalled when a tab finishes loading a URL
+    settled: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct CrossTabState {
     /// Shared immutable resources (content-addressed)
     resources: HashMap<u64, SharedResource>,
     /// Reference counts
     ref_counts: HashMap<u64, usize>,
+    /// Bytes held by `resources`
+    bytes: usize,
+    /// Bytes past which resources are evicted
+    capacity: usize,
+    /// What each URL last loaded as
+    index: HashMap<String, IndexedUrl>,
+    /// URLs indexed to each resource
+    urls: HashMap<u64, HashSet<String>>,
+    /// Resources by when they were last used, oldest first
+    recency: BTreeMap<u64, u64>,
+    /// Each resource's key in `recency`
+    last_used: HashMap<u64, u64>,
+    /// Next `recency` key
+    clock: u64,
+    /// URLs a tab is loading right now
+    in_flight: HashSet<String>,
+    /// Tabs told about changed URLs
+    subscribers: Vec<mpsc::Sender<String>>,
+}
+
+/// A URL's entry in the index
+#[derive(Debug, Clone)]
+struct IndexedUrl {
+    hash: u64,
+    validators: CacheValidators,
+    stored_at: Instant,
+    max_age: Duration,
+    hits: u32,
+}
+
+impl IndexedUrl {
+    fn is_fresh(&self) -> bool {
+        self.stored_at.elapsed() < self.max_age
+    }
 }
 
 /// Shared resource
@@ -226,17 +287,198 @@ pub struct CrossTabCache {
 pub struct SharedResource {
     pub hash: u64,
     pub content_type: String,
-    pub data: Vec<u8>,
+    pub data: Arc<Vec<u8>>,
     pub immutable: bool,
 }
 
+/// A URL in the cross-tab index, for listing what is cached
+#[derive(Debug, Clone)]
+pub struct CrossTabEntry {
+    pub url: String,
+    pub content_type: String,
+    /// Body size in bytes
+    pub size: usize,
+    /// Time left before the copy goes stale
+    pub ttl: Duration,
+    /// Times the copy was served fresh
+    pub hits: u32,
+}
+
+/// Outcome of looking a URL up in the cross-tab index
+#[derive(Debug)]
+pub enum CrossTabLookup {
+    /// A fresh copy some tab loaded
+    Fresh(SharedResource),
+    /// This tab loads the URL for everyone
+    Load(CrossTabLoad),
+}
+
+/// A tab's turn to load a URL; other tabs asking for it wait. Finish it
+/// with `not_modified` or `complete` to share the answer, or drop it to
+/// let the next tab try.
+#[derive(Debug)]
+pub struct CrossTabLoad {
+    cache: CrossTabCache,
+    url: String,
+    validators: Option<CacheValidators>,
+}
+
+impl Default for CrossTabCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CrossTabCache {
     pub fn new() -> Self {
-        Self::default()
+        Self::with_capacity(CROSS_TAB_CAPACITY)
+    }
+    
+    /// Index holding at most `capacity` bytes of URL content
+    pub fn with_capacity(capacity: usize) -> Self {
+        let cache = Self { inner: Arc::default() };
+        cache.state().capacity = capacity;
+        cache
+    }
+    
+    fn state(&self) -> MutexGuard<'_, CrossTabState> {
+        self.inner.state.lock().unwrap()
     }
     
     /// Store resource (content-addressed)
-    pub fn store(&mut self, content_type: &str, data: Vec<u8>) -> u64 {
+    pub fn store(&self, content_type: &str, data: Vec<u8>) -> u64 {
+        let mut state = self.state();
+        let hash = state.store(content_type, data);
+        state.evict();
+        hash
+    }
+    
+    /// Get resource by hash
+    pub fn get(&self, hash: u64) -> Option<SharedResource> {
+        self.state().resources.get(&hash).cloned()
+    }
+    
+    /// Release reference
+    pub fn release(&self, hash: u64) {
+        self.state().release(hash);
+    }
+    
+    /// Memory saved by sharing
+    pub fn memory_saved(&self) -> usize {
+        let state = self.state();
+        state.resources.values()
+            .filter_map(|r| {
+                let refs = state.ref_counts.get(&r.hash).copied().unwrap_or(0);
+                if refs > 1 {
+                    Some(r.data.len() * (refs - 1))
+                } else {
+                    None
+                }
+            })
+            .sum()
+    }
+    
+    /// A fresh copy of `url`, or this tab's turn to load it. While another
+    /// tab is loading it, waits for that answer rather than asking too.
+    pub fn lookup(&self, url: &str) -> CrossTabLookup {
+        let started = Instant::now();
+        let mut state = self.state();
+        loop {
+            if let Some(resource) = state.fresh(url) {
+                return CrossTabLookup::Fresh(resource);
+            }
+            let waited = started.elapsed();
+            if !state.in_flight.contains(url) || waited >= SINGLE_FLIGHT_TIMEOUT {
+                break;
+            }
+            state = self.inner.settled.wait_timeout(state, SINGLE_FLIGHT_TIMEOUT - waited).unwrap().0;
+        }
+        
+        state.in_flight.insert(url.to_string());
+        let validators = state.index.get(url)
+            .map(|entry| entry.validators.clone())
+            .filter(|validators| !validators.is_empty());
+        CrossTabLookup::Load(CrossTabLoad {
+            cache: self.clone(),
+            url: url.to_string(),
+            validators,
+        })
+    }
+    
+    /// Forget `url` and tell subscribers, e.g. after a request that
+    /// changes it; false if it was not indexed
+    pub fn invalidate(&self, url: &str) -> bool {
+        let mut state = self.state();
+        let indexed = state.unindex(url).is_some();
+        state.notify(url);
+        indexed
+    }
+    
+    /// Forget every URL, telling subscribers
+    pub fn clear(&self) {
+        let mut state = self.state();
+        let urls: Vec<String> = state.index.keys().cloned().collect();
+        for url in urls {
+            state.unindex(&url);
+            state.notify(&url);
+        }
+    }
+    
+    /// Forget stale URLs that have no validators to revalidate with
+    pub fn cleanup(&self) {
+        let mut state = self.state();
+        let stale: Vec<String> = state.index.iter()
+            .filter(|(_, entry)| !entry.is_fresh() && entry.validators.is_empty())
+            .map(|(url, _)| url.clone())
+            .collect();
+        for url in stale {
+            state.unindex(&url);
+        }
+    }
+    
+    /// Whether `url` is indexed, fresh or not
+    pub fn contains(&self, url: &str) -> bool {
+        self.state().index.contains_key(url)
+    }
+    
+    /// Every indexed URL
+    pub fn entries(&self) -> Vec<CrossTabEntry> {
+        let state = self.state();
+        state.index.iter()
+            .filter_map(|(url, entry)| {
+                let resource = state.resources.get(&entry.hash)?;
+                Some(CrossTabEntry {
+                    url: url.clone(),
+                    content_type: resource.content_type.clone(),
+                    size: resource.data.len(),
+                    ttl: entry.max_age.saturating_sub(entry.stored_at.elapsed()),
+                    hits: entry.hits,
+                })
+            })
+            .collect()
+    }
+    
+    /// Indexed URLs and bytes held; there is no cap on the URL count
+    pub fn stats(&self) -> CacheStats {
+        let state = self.state();
+        CacheStats {
+            entry_count: state.index.len(),
+            total_size: state.bytes,
+            max_entries: usize::MAX,
+            max_size: state.capacity,
+        }
+    }
+    
+    /// URLs whose cached content changed or was invalidated from now on
+    pub fn subscribe(&self) -> mpsc::Receiver<String> {
+        let (tx, rx) = mpsc::channel();
+        self.state().subscribers.push(tx);
+        rx
+    }
+}
+
+impl CrossTabState {
+    fn store(&mut self, content_type: &str, data: Vec<u8>) -> u64 {
         use std::hash::{Hash, Hasher};
         use std::collections::hash_map::DefaultHasher;
         
@@ -245,46 +487,140 @@ impl CrossTabCache {
         let hash = hasher.finish();
         
         if !self.resources.contains_key(&hash) {
+            self.bytes += data.len();
             self.resources.insert(hash, SharedResource {
                 hash,
                 content_type: content_type.to_string(),
-                data,
+                data: Arc::new(data),
                 immutable: true,
             });
         }
         
         *self.ref_counts.entry(hash).or_insert(0) += 1;
+        self.touch(hash);
         hash
     }
     
-    /// Get resource by hash
-    pub fn get(&self, hash: u64) -> Option<&SharedResource> {
-        self.resources.get(&hash)
-    }
-    
-    /// Release reference
-    pub fn release(&mut self, hash: u64) {
+    fn release(&mut self, hash: u64) {
         if let Some(count) = self.ref_counts.get_mut(&hash) {
             *count = count.saturating_sub(1);
             if *count == 0 {
-                self.resources.remove(&hash);
-                self.ref_counts.remove(&hash);
+                self.forget(hash);
             }
         }
     }
     
-    /// Memory saved by sharing
-    pub fn memory_saved(&self) -> usize {
-        self.resources.values()
-            .filter_map(|r| {
-                let refs = self.ref_counts.get(&r.hash).copied().unwrap_or(0);
-                if refs > 1 {
-                    Some(r.data.len() * (refs - 1))
-                } else {
-                    None
-                }
-            })
-            .sum()
+    /// Drop a resource and its bookkeeping
+    fn forget(&mut self, hash: u64) {
+        if let Some(resource) = self.resources.remove(&hash) {
+            self.bytes -= resource.data.len();
+        }
+        self.ref_counts.remove(&hash);
+        if let Some(used) = self.last_used.remove(&hash) {
+            self.recency.remove(&used);
+        }
+    }
+    
+    /// Mark a resource as just used
+    fn touch(&mut self, hash: u64) {
+        if let Some(previous) = self.last_used.insert(hash, self.clock) {
+            self.recency.remove(&previous);
+        }
+        self.recency.insert(self.clock, hash);
+        self.clock += 1;
+    }
+    
+    fn index_url(&mut self, url: &str, entry: IndexedUrl) {
+        self.urls.entry(entry.hash).or_default().insert(url.to_string());
+        self.index.insert(url.to_string(), entry);
+    }
+    
+    /// Take `url` out of the index, releasing its resource
+    fn unindex(&mut self, url: &str) -> Option<IndexedUrl> {
+        let entry = self.index.remove(url)?;
+        if let Some(urls) = self.urls.get_mut(&entry.hash) {
+            urls.remove(url);
+            if urls.is_empty() {
+                self.urls.remove(&entry.hash);
+            }
+        }
+        self.release(entry.hash);
+        Some(entry)
+    }
+    
+    fn fresh(&mut self, url: &str) -> Option<SharedResource> {
+        let entry = self.index.get_mut(url).filter(|entry| entry.is_fresh())?;
+        entry.hits += 1;
+        let hash = entry.hash;
+        self.touch(hash);
+        self.resources.get(&hash).cloned()
+    }
+    
+    /// Drop least recently used resources, whoever holds them, and the
+    /// URLs indexed to them until under the byte cap
+    fn evict(&mut self) {
+        while self.bytes > self.capacity {
+            let Some(&hash) = self.recency.values().next() else { break };
+            for url in self.urls.remove(&hash).unwrap_or_default() {
+                self.index.remove(&url);
+            }
+            self.forget(hash);
+        }
+    }
+    
+    fn notify(&mut self, url: &str) {
+        self.subscribers.retain(|tx| tx.send(url.to_string()).is_ok());
+    }
+}
+
+impl CrossTabLoad {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    
+    /// Validators to revalidate the copy another tab stored with
+    pub fn validators(&self) -> Option<&CacheValidators> {
+        self.validators.as_ref()
+    }
+    
+    /// The server answered 304: the stored copy is good for `max_age`
+    /// more. `None` if it was invalidated meanwhile.
+    pub fn not_modified(self, max_age: Duration) -> Option<SharedResource> {
+        let mut state = self.cache.state();
+        let entry = state.index.get_mut(&self.url)?;
+        entry.stored_at = Instant::now();
+        entry.max_age = max_age;
+        let hash = entry.hash;
+        state.touch(hash);
+        state.resources.get(&hash).cloned()
+    }
+    
+    /// Share a full response, fresh for `max_age`. Tabs are told if it
+    /// replaces different content.
+    pub fn complete(self, content_type: &str, data: Vec<u8>, validators: CacheValidators, max_age: Duration) -> SharedResource {
+        let mut state = self.cache.state();
+        let hash = state.store(content_type, data);
+        let previous = state.unindex(&self.url);
+        state.index_url(&self.url, IndexedUrl {
+            hash,
+            validators,
+            stored_at: Instant::now(),
+            max_age,
+            hits: 0,
+        });
+        if previous.is_some_and(|previous| previous.hash != hash) {
+            state.notify(&self.url);
+        }
+        let resource = state.resources[&hash].clone();
+        state.evict();
+        resource
+    }
+}
+
+impl Drop for CrossTabLoad {
+    fn drop(&mut self) {
+        self.cache.state().in_flight.remove(&self.url);
+        self.cache.inner.settled.notify_all();
     }
 }
 
@@ -325,7 +661,7 @@ mod tests {
     
     #[test]
     fn test_cross_tab_cache() {
-        let mut cache = CrossTabCache::new();
+        let cache = CrossTabCache::new();
         
         let data = b"Hello, World!".to_vec();
         let hash1 = cache.store("text/plain", data.clone());
@@ -334,4 +670,86 @@ mod tests {
         assert_eq!(hash1, hash2); // Same content = same hash
         assert!(cache.memory_saved() > 0); // Saved by deduplication
     }
+    
+    #[test]
+    fn test_cross_tab_single_flight() {
+        let cache = CrossTabCache::new();
+        let url = "https://example.com/app.js";
+        
+        let CrossTabLookup::Load(load) = cache.lookup(url) else { panic!("nothing cached yet") };
+        assert!(load.validators().is_none());
+        
+        // A second tab waits for the first tab's response instead of asking
+        let other_tab = cache.clone();
+        let waiter = std::thread::spawn(move || match other_tab.lookup(url) {
+            CrossTabLookup::Fresh(resource) => resource.data.to_vec(),
+            CrossTabLookup::Load(_) => panic!("loaded twice"),
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        let validators = CacheValidators { etag: Some("\"v1\"".into()), last_modified: None };
+        load.complete("text/javascript", b"v1".to_vec(), validators, Duration::from_secs(60));
+        assert_eq!(waiter.join().unwrap(), b"v1");
+    }
+    
+    #[test]
+    fn test_cross_tab_revalidation_and_invalidation() {
+        let cache = CrossTabCache::new();
+        let url = "https://example.com/";
+        let changes = cache.subscribe();
+        let etag = CacheValidators { etag: Some("\"v1\"".into()), last_modified: None };
+        
+        let CrossTabLookup::Load(load) = cache.lookup(url) else { panic!() };
+        load.complete("text/html", b"v1".to_vec(), etag.clone(), Duration::ZERO);
+        
+        // Stale: the next tab revalidates with the shared validators
+        let CrossTabLookup::Load(load) = cache.lookup(url) else { panic!("stale copy served") };
+        assert_eq!(load.validators().unwrap().etag.as_deref(), Some("\"v1\""));
+        let resource = load.not_modified(Duration::from_secs(60)).unwrap();
+        assert_eq!(*resource.data, b"v1");
+        assert!(matches!(cache.lookup(url), CrossTabLookup::Fresh(_)));
+        assert!(changes.try_recv().is_err());
+        
+        // Invalidation is pushed to subscribers
+        cache.invalidate(url);
+        assert_eq!(changes.try_recv().unwrap(), url);
+        let CrossTabLookup::Load(load) = cache.lookup(url) else { panic!("invalidated copy served") };
+        assert!(load.validators().is_none());
+        drop(load);
+        assert!(cache.get(resource.hash).is_none());
+    }
+    
+    #[test]
+    fn test_cross_tab_capacity() {
+        let cache = CrossTabCache::with_capacity(8);
+        let load = |url: &str, body: &[u8]| {
+            let CrossTabLookup::Load(load) = cache.lookup(url) else { panic!() };
+            load.complete("text/plain", body.to_vec(), CacheValidators { etag: None, last_modified: None }, Duration::from_secs(60))
+        };
+        
+        let a = load("https://example.com/a", b"aaaa");
+        let b = load("https://example.com/b", b"bbbb");
+        assert!(matches!(cache.lookup("https://example.com/a"), CrossTabLookup::Fresh(_)));
+        
+        // Over the cap, the least recently used URL goes
+        load("https://example.com/c", b"cccc");
+        assert!(cache.get(b.hash).is_none());
+        assert!(cache.get(a.hash).is_some());
+        assert!(matches!(cache.lookup("https://example.com/b"), CrossTabLookup::Load(_)));
+        
+        // Content shared by two URLs goes with both of them
+        let shared = load("https://example.com/a2", b"aaaa");
+        assert_eq!(shared.hash, a.hash);
+        load("https://example.com/d", b"dddd");
+        load("https://example.com/e", b"eeee");
+        assert!(cache.get(a.hash).is_none());
+        assert!(!cache.contains("https://example.com/a"));
+        assert!(!cache.contains("https://example.com/a2"));
+        
+        // So do bytes stored outside the index
+        let stored = cache.store("text/plain", b"ffff".to_vec());
+        cache.store("text/plain", b"gggg".to_vec());
+        cache.store("text/plain", b"hhhh".to_vec());
+        assert!(cache.get(stored).is_none());
+        assert!(cache.stats().total_size <= 8);
+    }
 }
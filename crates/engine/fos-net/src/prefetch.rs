@@ -190,6 +190,8 @@ pub struct PrefetchManager {
     stats: PrefetchStats,
     /// Prefetch threshold probability
     threshold: f32,
+    /// Data saver is on: only user-initiated prefetches run
+    save_data: bool,
 }
 
 /// Prefetch statistics
@@ -231,6 +233,7 @@ impl PrefetchManager {
             current_fetching: 0,
             stats: PrefetchStats::default(),
             threshold: 0.1,
+            save_data: false,
         }
     }
     
@@ -246,6 +249,22 @@ impl PrefetchManager {
         self
     }
     
+    /// Follow the data saver preference; turning it on drops queued
+    /// speculative prefetches
+    pub fn set_save_data(&mut self, enabled: bool) {
+        self.save_data = enabled;
+        if enabled {
+            let states = &mut self.states;
+            self.pending.retain(|req| {
+                let keep = req.priority == Priority::Critical;
+                if !keep {
+                    states.remove(&req.url);
+                }
+                keep
+            });
+        }
+    }
+    
     /// Register a link
     pub fn register_link(&mut self, id: LinkId, url: ResourceUrl) {
         self.link_urls.insert(id, url);
@@ -295,6 +314,11 @@ impl PrefetchManager {
     
     /// Queue a prefetch request
     pub fn queue_prefetch(&mut self, url: ResourceUrl, priority: Priority, probability: f32) {
+        // Speculation costs data the user asked to save
+        if self.save_data && priority < Priority::Critical {
+            return;
+        }
+        
         // Skip if already fetched or fetching
         if let Some(state) = self.states.get(&url) {
             match state {
@@ -467,4 +491,19 @@ mod tests {
         
         assert!(manager.is_prefetched("https://test.com"));
     }
+    
+    #[test]
+    fn test_save_data_skips_speculation() {
+        let mut manager = PrefetchManager::new().with_threshold(0.0);
+        manager.register_link(1, "https://example.com/a".into());
+        manager.on_hover(1);
+        manager.queue_prefetch("https://example.com/b".into(), Priority::Critical, 1.0);
+        
+        manager.set_save_data(true);
+        assert!(manager.state("https://example.com/a").is_none());
+        manager.on_click(1);
+        manager.queue_prefetch("https://example.com/c".into(), Priority::High, 0.9);
+        assert_eq!(manager.next_request().unwrap().url, "https://example.com/b");
+        assert!(manager.next_request().is_none());
+    }
 }
@@ -55,7 +55,7 @@ impl RequestPriority {
 }
 
 /// Resource types for priority classification
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ResourceType {
     Document,
     Style,
@@ -64,6 +64,7 @@ pub enum ResourceType {
     Font,
     Media,
     Prefetch,
+    #[default]
     Other,
 }
 
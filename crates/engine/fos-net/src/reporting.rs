@@ -0,0 +1,319 @@
+//! Reporting API delivery
+//!
+//! Endpoint configuration from `Reporting-Endpoints` and legacy `Report-To`
+//! headers, and batched upload of queued reports as `application/reports+json`.
+
+use crate::NetError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// MIME type of report uploads
+pub const REPORTS_CONTENT_TYPE: &str = "application/reports+json";
+/// Uploads attempted before a report is dropped
+pub const MAX_DELIVERY_ATTEMPTS: u32 = 3;
+/// Backoff after the first failed upload to an endpoint; doubles per failure
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Named endpoint from `Reporting-Endpoints`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportingEndpoint {
+    pub name: String,
+    pub url: String,
+}
+
+/// Endpoint group from the legacy `Report-To` header
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct EndpointGroup {
+    #[serde(default = "default_group")]
+    pub group: String,
+    pub max_age: u64,
+    #[serde(default)]
+    pub include_subdomains: bool,
+    pub endpoints: Vec<EndpointUrl>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct EndpointUrl {
+    pub url: String,
+}
+
+fn default_group() -> String { "default".to_string() }
+
+/// Only secure endpoints receive reports
+fn is_potentially_trustworthy(url: &str) -> bool {
+    url.starts_with("https://")
+        || url.starts_with("http://localhost")
+        || url.starts_with("http://127.0.0.1")
+}
+
+/// Parse a `Reporting-Endpoints` structured-field dictionary
+/// (`default="https://r.example/", csp="/csp-reports"`); paths resolve
+/// against `origin`
+pub fn parse_reporting_endpoints(value: &str, origin: &str) -> Vec<ReportingEndpoint> {
+    let mut endpoints: Vec<ReportingEndpoint> = Vec::new();
+    for member in value.split(',') {
+        let Some((name, url)) = member.split_once('=') else { continue };
+        let name = name.trim();
+        // Drop parameters, then require an sf-string
+        let url = url.split(';').next().unwrap_or("").trim();
+        let Some(url) = url.strip_prefix('"').and_then(|u| u.strip_suffix('"')) else { continue };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-.*".contains(c)) {
+            continue;
+        }
+        let url = if url.starts_with('/') { format!("{}{}", origin.trim_end_matches('/'), url) } else { url.to_string() };
+        if !is_potentially_trustworthy(&url) { continue; }
+        // Later members override earlier ones
+        endpoints.retain(|e| e.name != name);
+        endpoints.push(ReportingEndpoint { name: name.to_string(), url });
+    }
+    endpoints
+}
+
+/// Parse a `Report-To` header: comma-separated JSON endpoint groups
+pub fn parse_report_to(value: &str) -> Vec<EndpointGroup> {
+    let groups: Vec<EndpointGroup> = serde_json::from_str(&format!("[{}]", value)).unwrap_or_default();
+    groups.into_iter()
+        .map(|mut g| {
+            g.endpoints.retain(|e| is_potentially_trustworthy(&e.url));
+            g
+        })
+        .filter(|g| !g.endpoints.is_empty())
+        .collect()
+}
+
+/// Value in a report body
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReportValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl ReportValue {
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Null => serde_json::Value::Null,
+            Self::Bool(b) => (*b).into(),
+            Self::Int(i) => (*i).into(),
+            Self::Float(f) => serde_json::Number::from_f64(*f).map_or(serde_json::Value::Null, Into::into),
+            Self::Str(s) => s.clone().into(),
+        }
+    }
+}
+
+/// Report waiting for upload
+#[derive(Debug, Clone)]
+pub struct QueuedReport {
+    pub report_type: String,
+    /// Document URL the report is about
+    pub url: String,
+    pub user_agent: String,
+    pub body: Vec<(String, ReportValue)>,
+    /// Endpoint URL
+    pub destination: String,
+    pub generated: Instant,
+    attempts: u32,
+}
+
+impl QueuedReport {
+    pub fn new(report_type: &str, url: &str, user_agent: &str, body: Vec<(String, ReportValue)>, destination: &str) -> Self {
+        Self {
+            report_type: report_type.to_string(),
+            url: url.to_string(),
+            user_agent: user_agent.to_string(),
+            body,
+            destination: destination.to_string(),
+            generated: Instant::now(),
+            attempts: 0,
+        }
+    }
+
+    pub fn attempts(&self) -> u32 { self.attempts }
+
+    fn to_json(&self, now: Instant) -> serde_json::Value {
+        let body: serde_json::Map<String, serde_json::Value> =
+            self.body.iter().map(|(k, v)| (k.clone(), v.to_json())).collect();
+        serde_json::json!({
+            "age": now.saturating_duration_since(self.generated).as_millis() as u64,
+            "type": self.report_type,
+            "url": self.url,
+            "user_agent": self.user_agent,
+            "body": body,
+        })
+    }
+}
+
+/// Serialize reports into one upload body
+pub fn serialize_reports(reports: &[QueuedReport], now: Instant) -> Vec<u8> {
+    let list: Vec<serde_json::Value> = reports.iter().map(|r| r.to_json(now)).collect();
+    serde_json::to_vec(&list).unwrap_or_default()
+}
+
+/// Sends report uploads
+pub trait ReportTransport {
+    /// POST `body` and return the response status
+    fn post(&mut self, url: &str, content_type: &str, body: Vec<u8>) -> Result<u16, NetError>;
+}
+
+impl ReportTransport for crate::client::blocking::Client {
+    fn post(&mut self, url: &str, content_type: &str, body: Vec<u8>) -> Result<u16, NetError> {
+        let headers = vec![("Content-Type".to_string(), content_type.to_string())];
+        self.request("POST", url, Some(headers), Some(body)).map(|r| r.status)
+    }
+}
+
+/// Result of a delivery pass
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeliveryOutcome {
+    pub delivered: usize,
+    /// Reports given up on after repeated failures
+    pub dropped: usize,
+    /// Endpoints that answered 410 Gone and should be forgotten
+    pub gone: Vec<String>,
+}
+
+/// Per-endpoint failure backoff
+#[derive(Debug, Clone, Copy)]
+struct EndpointBackoff {
+    failures: u32,
+    retry_at: Instant,
+}
+
+/// Queues reports and uploads them in per-endpoint batches
+#[derive(Debug)]
+pub struct ReportUploader {
+    queue: Vec<QueuedReport>,
+    /// Reports wait this long so several can share an upload
+    batch_delay: Duration,
+    backoff: HashMap<String, EndpointBackoff>,
+}
+
+impl ReportUploader {
+    pub fn new() -> Self {
+        Self { queue: Vec::new(), batch_delay: Duration::from_secs(60), backoff: HashMap::new() }
+    }
+
+    pub fn with_batch_delay(batch_delay: Duration) -> Self {
+        Self { batch_delay, ..Self::new() }
+    }
+
+    pub fn queue(&mut self, report: QueuedReport) {
+        self.queue.push(report);
+    }
+
+    pub fn pending(&self) -> usize { self.queue.len() }
+
+    /// Upload every endpoint whose oldest report waited out the batch delay
+    pub fn deliver(&mut self, transport: &mut dyn ReportTransport, now: Instant) -> DeliveryOutcome {
+        let mut batches: HashMap<String, Vec<QueuedReport>> = HashMap::new();
+        for report in std::mem::take(&mut self.queue) {
+            batches.entry(report.destination.clone()).or_default().push(report);
+        }
+
+        let mut outcome = DeliveryOutcome::default();
+        for (endpoint, mut reports) in batches {
+            let oldest = reports.iter().map(|r| r.generated).min().unwrap_or(now);
+            let backing_off = self.backoff.get(&endpoint).is_some_and(|b| b.retry_at > now);
+            if now.saturating_duration_since(oldest) < self.batch_delay || backing_off {
+                self.queue.extend(reports);
+                continue;
+            }
+
+            let body = serialize_reports(&reports, now);
+            match transport.post(&endpoint, REPORTS_CONTENT_TYPE, body) {
+                Ok(status) if (200..300).contains(&status) => {
+                    self.backoff.remove(&endpoint);
+                    outcome.delivered += reports.len();
+                }
+                Ok(410) => {
+                    self.backoff.remove(&endpoint);
+                    outcome.dropped += reports.len();
+                    outcome.gone.push(endpoint);
+                }
+                _ => {
+                    let backoff = self.backoff.entry(endpoint).or_insert(EndpointBackoff { failures: 0, retry_at: now });
+                    backoff.failures += 1;
+                    backoff.retry_at = now + RETRY_DELAY.max(self.batch_delay) * 2u32.pow(backoff.failures.min(6) - 1);
+                    for report in &mut reports { report.attempts += 1; }
+                    let before = reports.len();
+                    reports.retain(|r| r.attempts < MAX_DELIVERY_ATTEMPTS);
+                    outcome.dropped += before - reports.len();
+                    self.queue.extend(reports);
+                }
+            }
+        }
+        outcome
+    }
+}
+
+impl Default for ReportUploader {
+    fn default() -> Self { Self::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_headers() {
+        let endpoints = parse_reporting_endpoints(
+            r#"default="https://r.example/reports", csp="/csp";x=1, bad="http://insecure.example/", default="https://r2.example/""#,
+            "https://site.example",
+        );
+        assert_eq!(endpoints, vec![
+            ReportingEndpoint { name: "csp".into(), url: "https://site.example/csp".into() },
+            ReportingEndpoint { name: "default".into(), url: "https://r2.example/".into() },
+        ]);
+
+        let groups = parse_report_to(
+            r#"{"group":"network-errors","max_age":86400,"endpoints":[{"url":"https://n.example/"}]}, {"max_age":60,"endpoints":[{"url":"http://insecure.example/"}]}"#,
+        );
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].group, "network-errors");
+        assert_eq!(groups[0].endpoints[0].url, "https://n.example/");
+        assert!(parse_report_to("not json").is_empty());
+    }
+
+    struct Recorder { status: u16, uploads: Vec<(String, serde_json::Value)> }
+
+    impl ReportTransport for Recorder {
+        fn post(&mut self, url: &str, content_type: &str, body: Vec<u8>) -> Result<u16, NetError> {
+            assert_eq!(content_type, REPORTS_CONTENT_TYPE);
+            self.uploads.push((url.to_string(), serde_json::from_slice(&body).unwrap()));
+            Ok(self.status)
+        }
+    }
+
+    #[test]
+    fn test_batched_delivery_and_retry() {
+        let mut uploader = ReportUploader::with_batch_delay(Duration::ZERO);
+        let body = vec![("id".to_string(), ReportValue::Str("old-api".into())), ("lineNumber".to_string(), ReportValue::Int(3))];
+        for _ in 0..2 {
+            uploader.queue(QueuedReport::new("deprecation", "https://site.example/", "fOS", body.clone(), "https://r.example/"));
+        }
+        uploader.queue(QueuedReport::new("crash", "https://site.example/", "fOS", Vec::new(), "https://crash.example/"));
+
+        let mut transport = Recorder { status: 500, uploads: Vec::new() };
+        let outcome = uploader.deliver(&mut transport, Instant::now());
+        assert_eq!((outcome.delivered, transport.uploads.len(), uploader.pending()), (0, 2, 3));
+        let (_, batch) = transport.uploads.iter().find(|(url, _)| url == "https://r.example/").unwrap();
+        assert_eq!(batch.as_array().unwrap().len(), 2);
+        assert_eq!(batch[0]["type"], "deprecation");
+        assert_eq!(batch[0]["body"]["lineNumber"], 3);
+
+        // Backing off: nothing is sent until the retry time
+        transport.status = 200;
+        assert_eq!(uploader.deliver(&mut transport, Instant::now()).delivered, 0);
+        let later = Instant::now() + Duration::from_secs(5);
+        assert_eq!(uploader.deliver(&mut transport, later).delivered, 3);
+        assert_eq!(uploader.pending(), 0);
+
+        transport.status = 410;
+        uploader.queue(QueuedReport::new("crash", "https://site.example/", "fOS", Vec::new(), "https://crash.example/"));
+        let outcome = uploader.deliver(&mut transport, later);
+        assert_eq!(outcome.gone, vec!["https://crash.example/".to_string()]);
+    }
+}
@@ -5,7 +5,9 @@
 
 use std::io::{self, Read, Write, BufReader, BufWriter};
 use std::net::{TcpStream as StdTcpStream, ToSocketAddrs, SocketAddr, Shutdown};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use crate::deadline::Deadline;
 
 /// TCP connection configuration
 #[derive(Debug, Clone)]
@@ -47,6 +49,8 @@ pub struct TcpConnection {
     local_addr: SocketAddr,
     /// Connection config
     config: TcpConfig,
+    /// Deadline of the request using the connection
+    deadline: Deadline,
 }
 
 impl TcpConnection {
@@ -80,6 +84,7 @@ impl TcpConnection {
             remote_addr: addr,
             local_addr,
             config,
+            deadline: Deadline::none(),
         })
     }
     
@@ -115,19 +120,60 @@ impl TcpConnection {
             remote_addr: self.remote_addr,
             local_addr: self.local_addr,
             config: self.config.clone(),
+            deadline: self.deadline.clone(),
         })
     }
+    
+    /// Bound reads and writes by `deadline` as well as the configured
+    /// timeouts; a cancellable deadline is noticed while blocked
+    pub fn set_deadline(&mut self, deadline: Deadline) -> io::Result<()> {
+        if deadline.is_unbounded() {
+            self.stream.set_read_timeout(self.config.read_timeout)?;
+            self.stream.set_write_timeout(self.config.write_timeout)?;
+        }
+        self.deadline = deadline;
+        Ok(())
+    }
+    
+    /// Run a blocking socket call in waits short enough to notice the
+    /// deadline, giving up after `timeout` without progress
+    fn within_deadline<T>(
+        &mut self,
+        timeout: Option<Duration>,
+        set_timeout: fn(&StdTcpStream, Option<Duration>) -> io::Result<()>,
+        mut op: impl FnMut(&mut StdTcpStream) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let started = Instant::now();
+        loop {
+            self.deadline.check_io()?;
+            let idle_left = timeout.map(|t| t.saturating_sub(started.elapsed()));
+            if idle_left == Some(Duration::ZERO) {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "socket timed out"));
+            }
+            set_timeout(&self.stream, self.deadline.next_wait(idle_left))?;
+            match op(&mut self.stream) {
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => continue,
+                result => return result,
+            }
+        }
+    }
 }
 
 impl Read for TcpConnection {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.stream.read(buf)
+        if self.deadline.is_unbounded() {
+            return self.stream.read(buf);
+        }
+        self.within_deadline(self.config.read_timeout, StdTcpStream::set_read_timeout, |stream| stream.read(buf))
     }
 }
 
 impl Write for TcpConnection {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.stream.write(buf)
+        if self.deadline.is_unbounded() {
+            return self.stream.write(buf);
+        }
+        self.within_deadline(self.config.write_timeout, StdTcpStream::set_write_timeout, |stream| stream.write(buf))
     }
     
     fn flush(&mut self) -> io::Result<()> {
@@ -281,4 +327,30 @@ mod tests {
         let addr = resolve_host("127.0.0.1", 80).unwrap();
         assert_eq!(addr.port(), 80);
     }
+    
+    #[test]
+    fn test_read_within_deadline() {
+        use crate::deadline::CancelToken;
+        
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut conn = TcpConnection::connect_to_addr(listener.local_addr().unwrap(), TcpConfig::default()).unwrap();
+        let _server = listener.accept().unwrap();
+        let mut buf = [0u8; 16];
+        
+        // The peer never answers, so the read ends with the deadline
+        let started = Instant::now();
+        conn.set_deadline(Deadline::after(Duration::from_millis(100))).unwrap();
+        assert_eq!(conn.read(&mut buf).unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        
+        // Cancelling from another thread wakes the blocked read
+        let token = CancelToken::new();
+        conn.set_deadline(Deadline::none().with_token(token.clone())).unwrap();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            token.cancel();
+        });
+        assert_eq!(conn.read(&mut buf).unwrap_err().kind(), io::ErrorKind::ConnectionAborted);
+        canceller.join().unwrap();
+    }
 }
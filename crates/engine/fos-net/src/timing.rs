@@ -0,0 +1,108 @@
+//! Request Timing
+//!
+//! Phase breakdown of one HTTP exchange, as shown by network inspectors and
+//! HAR files: DNS lookup, TCP connect, TLS handshake, sending the request,
+//! waiting for the first response byte and downloading the rest.
+
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+
+/// Time spent in each phase of a request, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RequestTimings {
+    /// DNS lookup; `None` when no lookup was made
+    pub dns_ms: Option<f64>,
+    /// TCP connect, excluding the TLS handshake
+    pub connect_ms: Option<f64>,
+    /// TLS handshake; `None` for plain HTTP
+    pub tls_ms: Option<f64>,
+    /// Writing the request
+    pub send_ms: f64,
+    /// Waiting for the first response byte
+    pub wait_ms: f64,
+    /// Reading the rest of the response
+    pub receive_ms: f64,
+}
+
+impl RequestTimings {
+    /// Sum of all phases
+    pub fn total_ms(&self) -> f64 {
+        self.dns_ms.unwrap_or(0.0)
+            + self.connect_ms.unwrap_or(0.0)
+            + self.tls_ms.unwrap_or(0.0)
+            + self.send_ms
+            + self.wait_ms
+            + self.receive_ms
+    }
+    
+    /// Time to first byte from the start of the request
+    pub fn ttfb_ms(&self) -> f64 {
+        self.total_ms() - self.receive_ms
+    }
+}
+
+/// Milliseconds since `start`
+pub fn elapsed_ms(start: Instant) -> f64 {
+    duration_ms(start.elapsed())
+}
+
+fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Reader that notes when the first byte arrives, to split waiting from
+/// downloading
+pub struct TimedReader<R> {
+    inner: R,
+    first_byte: Option<Instant>,
+}
+
+impl<R: Read> TimedReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, first_byte: None }
+    }
+    
+    /// When the first byte was read
+    pub fn first_byte(&self) -> Option<Instant> {
+        self.first_byte
+    }
+    
+    /// Fill the wait and receive phases of `timings` for a response that
+    /// started being awaited at `sent`
+    pub fn record(&self, sent: Instant, timings: &mut RequestTimings) {
+        let first_byte = self.first_byte.unwrap_or_else(Instant::now);
+        timings.wait_ms = duration_ms(first_byte.saturating_duration_since(sent));
+        timings.receive_ms = elapsed_ms(first_byte);
+    }
+}
+
+impl<R: Read> Read for TimedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 && self.first_byte.is_none() {
+            self.first_byte = Some(Instant::now());
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_timed_reader() {
+        let mut reader = TimedReader::new(&b"HTTP/1.1 200 OK\r\n"[..]);
+        let sent = Instant::now();
+        assert!(reader.first_byte().is_none());
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        assert!(reader.first_byte().is_some());
+        
+        let mut timings = RequestTimings { dns_ms: Some(2.0), send_ms: 1.0, ..Default::default() };
+        reader.record(sent, &mut timings);
+        assert!(timings.wait_ms >= 0.0 && timings.receive_ms >= 0.0);
+        assert!(timings.total_ms() >= 3.0);
+        assert!((timings.ttfb_ms() - (3.0 + timings.wait_ms)).abs() < 1e-9);
+    }
+}
@@ -2,14 +2,20 @@
 //!
 //! Production TLS support using rustls for secure connections.
 //! Includes session resumption and ALPN negotiation for HTTP/2.
+//! Certificate failures are reported as a [`TlsError`]; with certificate
+//! verification off, a chain that fails validation is still accepted but
+//! the handshake signatures are checked.
 
 use std::io::{self, Read, Write, BufReader};
-use std::net::TcpStream;
 use std::sync::Arc;
 
-use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
-use rustls::pki_types::ServerName;
+use rustls::{CertificateError, ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme, StreamOwned};
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
 
+use crate::client_cert::ClientCertManager;
+use crate::deadline::Deadline;
 use crate::tcp::TcpConnection;
 
 /// TLS configuration
@@ -19,8 +25,11 @@ pub struct TlsConfig {
     pub session_resumption: bool,
     /// ALPN protocols (e.g., ["h2", "http/1.1"])
     pub alpn_protocols: Vec<String>,
-    /// Verify certificates
+    /// Verify certificates; off for hosts the user let through despite a
+    /// certificate error
     pub verify_certs: bool,
+    /// Certificates for servers that ask for one
+    pub client_certs: Option<Arc<ClientCertManager>>,
 }
 
 impl Default for TlsConfig {
@@ -29,6 +38,7 @@ impl Default for TlsConfig {
             session_resumption: true,
             alpn_protocols: vec!["h2".into(), "http/1.1".into()],
             verify_certs: true,
+            client_certs: None,
         }
     }
 }
@@ -48,16 +58,141 @@ pub enum TlsState {
     Error,
 }
 
-/// Create the rustls client configuration
-fn create_client_config(config: &TlsConfig) -> Arc<ClientConfig> {
+/// Why a TLS connection failed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TlsError {
+    #[error("certificate has expired")]
+    CertificateExpired,
+    #[error("certificate is not valid yet")]
+    CertificateNotYetValid,
+    #[error("certificate is not issued by a trusted authority")]
+    UnknownIssuer,
+    #[error("certificate is not valid for {0}")]
+    NameMismatch(String),
+    #[error("certificate has been revoked")]
+    CertificateRevoked,
+    #[error("certificate is invalid: {0}")]
+    InvalidCertificate(String),
+    /// Not a certificate problem: protocol errors, alerts and the like
+    #[error("handshake failed: {0}")]
+    Handshake(String),
+}
+
+impl TlsError {
+    /// Classify a rustls error for a connection to `host`
+    pub fn from_rustls(error: &rustls::Error, host: &str) -> Self {
+        let rustls::Error::InvalidCertificate(cert) = error else {
+            return Self::Handshake(error.to_string());
+        };
+        match cert {
+            CertificateError::Expired | CertificateError::ExpiredContext { .. } => Self::CertificateExpired,
+            CertificateError::NotValidYet | CertificateError::NotValidYetContext { .. } => Self::CertificateNotYetValid,
+            CertificateError::UnknownIssuer => Self::UnknownIssuer,
+            CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. } => {
+                Self::NameMismatch(host.to_string())
+            }
+            CertificateError::Revoked => Self::CertificateRevoked,
+            other => Self::InvalidCertificate(format!("{:?}", other)),
+        }
+    }
+    
+    /// The TLS error behind an I/O error from a TLS stream, if any
+    pub fn from_io(error: &io::Error, host: &str) -> Option<Self> {
+        let inner = error.get_ref()?.downcast_ref::<rustls::Error>()?;
+        Some(Self::from_rustls(inner, host))
+    }
+    
+    /// Stable error code, as shown on error pages
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::CertificateExpired | Self::CertificateNotYetValid => "ERR_CERT_DATE_INVALID",
+            Self::UnknownIssuer => "ERR_CERT_AUTHORITY_INVALID",
+            Self::NameMismatch(_) => "ERR_CERT_COMMON_NAME_INVALID",
+            Self::CertificateRevoked => "ERR_CERT_REVOKED",
+            Self::InvalidCertificate(_) => "ERR_CERT_INVALID",
+            Self::Handshake(_) => "ERR_SSL_PROTOCOL_ERROR",
+        }
+    }
+    
+    /// The server's certificate failed validation
+    pub fn is_certificate_error(&self) -> bool {
+        !matches!(self, Self::Handshake(_))
+    }
+    
+    /// Whether the user may choose to connect anyway. Revoked certificates
+    /// and protocol failures cannot be overridden.
+    pub fn is_overridable(&self) -> bool {
+        self.is_certificate_error() && !matches!(self, Self::CertificateRevoked)
+    }
+}
+
+/// Verifier that accepts a certificate chain failing validation but still
+/// checks the handshake signatures made with it
+#[derive(Debug)]
+struct OverrideVerifier(Arc<WebPkiServerVerifier>);
+
+impl ServerCertVerifier for OverrideVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        match self.0.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now) {
+            Err(rustls::Error::InvalidCertificate(CertificateError::Revoked)) => {
+                Err(rustls::Error::InvalidCertificate(CertificateError::Revoked))
+            }
+            Err(rustls::Error::InvalidCertificate(_)) => Ok(ServerCertVerified::assertion()),
+            result => result,
+        }
+    }
+    
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls12_signature(message, cert, dss)
+    }
+    
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls13_signature(message, cert, dss)
+    }
+    
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_verify_schemes()
+    }
+}
+
+/// Create the rustls client configuration for a connection to `server_name`
+fn create_client_config(config: &TlsConfig, server_name: &str) -> Arc<ClientConfig> {
     let mut root_store = RootCertStore::empty();
     
     // Add Mozilla's root certificates
     root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
     
-    let mut tls_config = ClientConfig::builder()
-        .with_root_certificates(root_store)
-        .with_no_client_auth();
+    let builder = ClientConfig::builder();
+    let builder = if config.verify_certs {
+        builder.with_root_certificates(root_store)
+    } else {
+        let verifier = WebPkiServerVerifier::builder(Arc::new(root_store))
+            .build()
+            .expect("built-in roots are valid");
+        builder.dangerous()
+            .with_custom_certificate_verifier(Arc::new(OverrideVerifier(verifier)))
+    };
+    let mut tls_config = match &config.client_certs {
+        Some(manager) => builder.with_client_cert_resolver(manager.resolver(server_name)),
+        None => builder.with_no_client_auth(),
+    };
     
     // Configure ALPN protocols
     if !config.alpn_protocols.is_empty() {
@@ -79,7 +214,7 @@ fn create_client_config(config: &TlsConfig) -> Arc<ClientConfig> {
 /// TLS stream wrapper over TCP using rustls
 pub struct TlsStream {
     /// Rustls stream owning the connection
-    stream: StreamOwned<ClientConnection, TcpStream>,
+    stream: StreamOwned<ClientConnection, TcpConnection>,
     /// Connection state
     state: TlsState,
     /// Negotiated ALPN protocol
@@ -91,7 +226,7 @@ pub struct TlsStream {
 impl TlsStream {
     /// Connect with TLS to a host using an existing TCP connection
     pub fn connect(tcp: TcpConnection, server_name: &str, config: TlsConfig) -> io::Result<Self> {
-        let tls_config = create_client_config(&config);
+        let tls_config = create_client_config(&config, server_name);
         
         // Parse server name for SNI
         let server_name_parsed: ServerName<'static> = server_name
@@ -103,11 +238,9 @@ impl TlsStream {
         let conn = ClientConnection::new(tls_config, server_name_parsed)
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
         
-        // Take the inner TCP stream for rustls
-        let tcp_stream = tcp.into_inner();
-        
-        // Create the StreamOwned which handles handshake automatically
-        let mut stream = StreamOwned::new(conn, tcp_stream);
+        // Create the StreamOwned which handles handshake automatically; it
+        // keeps the TCP connection so reads stay bound by its deadline
+        let mut stream = StreamOwned::new(conn, tcp);
         
         // Perform handshake by doing a zero-byte write (forces handshake)
         // The StreamOwned will handle the handshake transparently
@@ -145,7 +278,12 @@ impl TlsStream {
         &self.server_name
     }
     
-
+    
+    
+    /// Bound reads and writes by `deadline`
+    pub fn set_deadline(&mut self, deadline: Deadline) -> io::Result<()> {
+        self.stream.sock.set_deadline(deadline)
+    }
     
     /// Get negotiated protocol version
     pub fn protocol_version(&self) -> Option<&'static str> {
@@ -211,10 +349,30 @@ mod tests {
         assert_ne!(TlsState::Connected, TlsState::Handshaking);
     }
     
+    #[test]
+    fn test_tls_error_classification() {
+        let expired = rustls::Error::InvalidCertificate(CertificateError::Expired);
+        let error = TlsError::from_rustls(&expired, "example.com");
+        assert_eq!(error, TlsError::CertificateExpired);
+        assert_eq!(error.code(), "ERR_CERT_DATE_INVALID");
+        assert!(error.is_overridable());
+        
+        let name = rustls::Error::InvalidCertificate(CertificateError::NotValidForName);
+        assert_eq!(TlsError::from_rustls(&name, "example.com"), TlsError::NameMismatch("example.com".into()));
+        
+        let revoked = io::Error::new(io::ErrorKind::InvalidData, rustls::Error::InvalidCertificate(CertificateError::Revoked));
+        let error = TlsError::from_io(&revoked, "example.com").unwrap();
+        assert!(error.is_certificate_error() && !error.is_overridable());
+        
+        let handshake = TlsError::from_rustls(&rustls::Error::HandshakeNotComplete, "example.com");
+        assert!(!handshake.is_certificate_error());
+        assert!(TlsError::from_io(&io::Error::from(io::ErrorKind::ConnectionReset), "example.com").is_none());
+    }
+    
     #[test]
     fn test_create_client_config() {
         let config = TlsConfig::default();
-        let client_config = create_client_config(&config);
+        let client_config = create_client_config(&config, "example.com");
         
         // Verify ALPN is configured
         assert_eq!(client_config.alpn_protocols.len(), 2);
@@ -0,0 +1,497 @@
+//! Streaming Uploads
+//!
+//! Request bodies read from their source a chunk at a time as the
+//! connection takes them, so a multi-gigabyte blob or file never has to sit
+//! in memory. HTTP/1.1 sends them with `Content-Length` when the size is
+//! known and chunked otherwise; HTTP/2 sends DATA frames no faster than the
+//! server's flow control windows allow. Upload progress events are reported
+//! as chunks go out, and an upload can be paused, resumed or aborted from
+//! another thread between chunks.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::http2::{Frame, Http2Connection, Http2Event};
+
+/// Bytes read from the source for each write
+pub const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Least time between progress events, as XMLHttpRequest specifies
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Where a request body is read from
+pub trait UploadSource: Send + Sync {
+    /// Length in bytes if known before sending; bodies of unknown length
+    /// are sent with chunked transfer encoding
+    fn size(&self) -> Option<u64>;
+    
+    /// Read the body from its first byte. Called for every time the body
+    /// is sent, so redirects and authentication challenges can send it again.
+    fn open(&self) -> io::Result<Box<dyn Read + Send>>;
+}
+
+/// Readers of unknown length
+impl<F> UploadSource for F
+where
+    F: Fn() -> io::Result<Box<dyn Read + Send>> + Send + Sync,
+{
+    fn size(&self) -> Option<u64> {
+        None
+    }
+    
+    fn open(&self) -> io::Result<Box<dyn Read + Send>> {
+        self()
+    }
+}
+
+/// Bytes already in memory, such as a blob's, shared rather than copied
+#[derive(Debug, Clone)]
+pub struct SharedBytes(pub Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl UploadSource for SharedBytes {
+    fn size(&self) -> Option<u64> {
+        Some(self.0.len() as u64)
+    }
+    
+    fn open(&self) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(io::Cursor::new(self.clone())))
+    }
+}
+
+/// A file, or a range of one, read from disk as it is sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSource {
+    path: PathBuf,
+    offset: u64,
+    length: u64,
+}
+
+impl FileSource {
+    /// The whole file at `path`, at its current length
+    pub fn new(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let length = std::fs::metadata(&path)?.len();
+        Ok(Self { path, offset: 0, length })
+    }
+    
+    /// Bytes `start..end` of this source, clamped like `Blob.slice()`
+    pub fn slice(&self, start: u64, end: u64) -> Self {
+        let end = end.min(self.length);
+        let start = start.min(end);
+        Self { path: self.path.clone(), offset: self.offset + start, length: end - start }
+    }
+}
+
+impl UploadSource for FileSource {
+    fn size(&self) -> Option<u64> {
+        Some(self.length)
+    }
+    
+    fn open(&self) -> io::Result<Box<dyn Read + Send>> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        Ok(Box::new(file.take(self.length)))
+    }
+}
+
+/// A request body streamed from its source
+#[derive(Clone)]
+pub struct UploadBody {
+    source: Arc<dyn UploadSource>,
+}
+
+impl UploadBody {
+    pub fn new(source: impl UploadSource + 'static) -> Self {
+        Self { source: Arc::new(source) }
+    }
+    
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self::new(SharedBytes(Arc::new(bytes)))
+    }
+    
+    pub fn from_file(path: impl Into<PathBuf>) -> io::Result<Self> {
+        Ok(Self::new(FileSource::new(path)?))
+    }
+    
+    pub fn size(&self) -> Option<u64> {
+        self.source.size()
+    }
+}
+
+impl fmt::Debug for UploadBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UploadBody").field("size", &self.size()).finish_non_exhaustive()
+    }
+}
+
+/// Upload progress event types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadEventKind {
+    LoadStart,
+    Progress,
+    Load,
+    Abort,
+    Error,
+    LoadEnd,
+}
+
+impl UploadEventKind {
+    /// Event type dispatched to `xhr.upload`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LoadStart => "loadstart",
+            Self::Progress => "progress",
+            Self::Load => "load",
+            Self::Abort => "abort",
+            Self::Error => "error",
+            Self::LoadEnd => "loadend",
+        }
+    }
+}
+
+/// An upload progress event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadProgress {
+    pub kind: UploadEventKind,
+    /// Body bytes sent so far
+    pub loaded: u64,
+    /// Body length, if known
+    pub total: Option<u64>,
+}
+
+impl UploadProgress {
+    pub fn length_computable(&self) -> bool {
+        self.total.is_some()
+    }
+}
+
+/// Receives upload progress events, on the thread sending the body
+pub type UploadListener = Arc<dyn Fn(&UploadProgress) + Send + Sync>;
+
+/// Pauses, resumes and aborts an upload from another thread
+#[derive(Debug, Clone, Default)]
+pub struct UploadControl {
+    state: Arc<(Mutex<ControlState>, Condvar)>,
+}
+
+#[derive(Debug, Default)]
+struct ControlState {
+    paused: bool,
+    aborted: bool,
+}
+
+impl UploadControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Stop sending after the chunk being written
+    pub fn pause(&self) {
+        self.state.0.lock().unwrap().paused = true;
+    }
+    
+    pub fn resume(&self) {
+        self.state.0.lock().unwrap().paused = false;
+        self.state.1.notify_all();
+    }
+    
+    /// Give up on the upload; the request fails after the chunk being written
+    pub fn abort(&self) {
+        self.state.0.lock().unwrap().aborted = true;
+        self.state.1.notify_all();
+    }
+    
+    pub fn is_paused(&self) -> bool {
+        self.state.0.lock().unwrap().paused
+    }
+    
+    pub fn is_aborted(&self) -> bool {
+        self.state.0.lock().unwrap().aborted
+    }
+    
+    /// Block while paused; `false` once aborted
+    fn wait_while_paused(&self) -> bool {
+        let (lock, resumed) = &*self.state;
+        let state = resumed.wait_while(lock.lock().unwrap(), |s| s.paused && !s.aborted).unwrap();
+        !state.aborted
+    }
+}
+
+/// Failure to send an upload
+#[derive(Debug, thiserror::Error)]
+pub enum UploadError {
+    #[error("Upload aborted")]
+    Aborted,
+    
+    #[error("Upload failed: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// A streamed request body with its progress listener and control
+#[derive(Clone)]
+pub struct Upload {
+    body: UploadBody,
+    control: UploadControl,
+    listener: Option<UploadListener>,
+}
+
+impl Upload {
+    pub fn new(body: UploadBody) -> Self {
+        Self { body, control: UploadControl::new(), listener: None }
+    }
+    
+    /// Controlled by `control` rather than one of its own
+    pub fn with_control(mut self, control: UploadControl) -> Self {
+        self.control = control;
+        self
+    }
+    
+    pub fn with_listener(mut self, listener: impl Fn(&UploadProgress) + Send + Sync + 'static) -> Self {
+        self.listener = Some(Arc::new(listener));
+        self
+    }
+    
+    pub fn body(&self) -> &UploadBody {
+        &self.body
+    }
+    
+    /// Handle to pause, resume or abort this upload
+    pub fn control(&self) -> UploadControl {
+        self.control.clone()
+    }
+    
+    /// Header framing the body in an HTTP/1.1 request
+    pub fn framing_header(&self) -> (&'static str, String) {
+        match self.body.size() {
+            Some(size) => ("Content-Length", size.to_string()),
+            None => ("Transfer-Encoding", "chunked".to_string()),
+        }
+    }
+    
+    /// Send the body of an HTTP/1.1 request whose head, with
+    /// [`framing_header`](Self::framing_header), was just written
+    pub fn write_http1<W: Write>(&self, writer: &mut W) -> Result<u64, UploadError> {
+        let chunked = self.body.size().is_none();
+        self.pump(&mut Http1Sink { writer, chunked })
+    }
+    
+    /// Send the body as DATA frames on `stream_id`, waiting for window
+    /// updates whenever the server's flow control windows are used up
+    pub fn write_h2<S: Read + Write>(&self, h2: &mut Http2Connection, stream: &mut S, stream_id: u32) -> Result<u64, UploadError> {
+        self.pump(&mut Http2Sink { h2, stream, stream_id })
+    }
+    
+    /// Move the body from its source into `sink`, reporting progress and
+    /// checking for pause and abort between chunks
+    fn pump(&self, sink: &mut dyn BodySink) -> Result<u64, UploadError> {
+        let total = self.body.size();
+        self.emit(UploadEventKind::LoadStart, 0, total);
+        
+        let mut loaded = 0u64;
+        let result = self.body.source.open().map_err(UploadError::from).and_then(|mut reader| {
+            let mut buf = vec![0u8; UPLOAD_CHUNK_SIZE];
+            let mut last_progress = Instant::now();
+            loop {
+                if !self.control.wait_while_paused() {
+                    return Err(UploadError::Aborted);
+                }
+                let room = sink.capacity()?.min(UPLOAD_CHUNK_SIZE);
+                let n = reader.read(&mut buf[..room])?;
+                if n == 0 {
+                    if total.is_some_and(|total| loaded != total) {
+                        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "body source ended early").into());
+                    }
+                    sink.send(&[], true)?;
+                    return Ok(());
+                }
+                sink.send(&buf[..n], false)?;
+                loaded += n as u64;
+                
+                if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                    self.emit(UploadEventKind::Progress, loaded, total);
+                    last_progress = Instant::now();
+                }
+            }
+        });
+        
+        match &result {
+            Ok(()) => {
+                self.emit(UploadEventKind::Progress, loaded, total);
+                self.emit(UploadEventKind::Load, loaded, total);
+            }
+            Err(UploadError::Aborted) => self.emit(UploadEventKind::Abort, loaded, total),
+            Err(UploadError::Io(_)) => self.emit(UploadEventKind::Error, loaded, total),
+        }
+        self.emit(UploadEventKind::LoadEnd, loaded, total);
+        result.map(|()| loaded)
+    }
+    
+    fn emit(&self, kind: UploadEventKind, loaded: u64, total: Option<u64>) {
+        if let Some(listener) = &self.listener {
+            listener(&UploadProgress { kind, loaded, total });
+        }
+    }
+}
+
+impl fmt::Debug for Upload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upload")
+            .field("body", &self.body)
+            .field("control", &self.control)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Connection a body is written to
+trait BodySink {
+    /// Bytes that may be sent now, waiting until some can be
+    fn capacity(&mut self) -> io::Result<usize> {
+        Ok(UPLOAD_CHUNK_SIZE)
+    }
+    
+    /// Send a chunk; the last call has an empty chunk and `last` set
+    fn send(&mut self, chunk: &[u8], last: bool) -> io::Result<()>;
+}
+
+struct Http1Sink<'a, W> {
+    writer: &'a mut W,
+    chunked: bool,
+}
+
+impl<W: Write> BodySink for Http1Sink<'_, W> {
+    fn send(&mut self, chunk: &[u8], last: bool) -> io::Result<()> {
+        if self.chunked {
+            if last {
+                self.writer.write_all(b"0\r\n\r\n")?;
+            } else {
+                write!(self.writer, "{:x}\r\n", chunk.len())?;
+                self.writer.write_all(chunk)?;
+                self.writer.write_all(b"\r\n")?;
+            }
+        } else {
+            self.writer.write_all(chunk)?;
+        }
+        if last {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+struct Http2Sink<'a, S> {
+    h2: &'a mut Http2Connection,
+    stream: &'a mut S,
+    stream_id: u32,
+}
+
+impl<S: Read + Write> BodySink for Http2Sink<'_, S> {
+    fn capacity(&mut self) -> io::Result<usize> {
+        loop {
+            let room = self.h2.send_capacity(self.stream_id);
+            if room > 0 {
+                return Ok(room);
+            }
+            
+            // Window used up: handle frames until the server opens it again
+            let frame = Frame::read_from(self.stream, self.h2.remote_settings.max_frame_size)
+                .map_err(io::Error::other)?;
+            match self.h2.process_frame(frame).map_err(io::Error::other)? {
+                Some(Http2Event::SettingsReceived) => self.h2.send_settings_ack(self.stream)?,
+                Some(Http2Event::Ping { ack: false, data }) => self.h2.send_ping_ack(self.stream, data)?,
+                Some(Http2Event::Headers { stream_id, .. } | Http2Event::Data { stream_id, .. }) if stream_id == self.stream_id => {
+                    return Err(io::Error::other("server responded before the request body was sent"));
+                }
+                Some(Http2Event::RstStream { stream_id, error_code }) if stream_id == self.stream_id => {
+                    return Err(io::Error::other(format!("H2 RST_STREAM: error {}", error_code)));
+                }
+                Some(Http2Event::GoAway { error_code, .. }) => {
+                    return Err(io::Error::other(format!("H2 GOAWAY: error {}", error_code)));
+                }
+                _ => {}
+            }
+        }
+    }
+    
+    fn send(&mut self, chunk: &[u8], last: bool) -> io::Result<()> {
+        self.h2.send_data(self.stream, self.stream_id, chunk, last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn recorder() -> (Arc<Mutex<Vec<UploadProgress>>>, impl Fn(&UploadProgress) + Send + Sync + 'static) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        (events, move |event: &UploadProgress| sink.lock().unwrap().push(*event))
+    }
+    
+    #[test]
+    fn test_upload_framing_and_progress() {
+        let data: Vec<u8> = (0..UPLOAD_CHUNK_SIZE + 10).map(|i| i as u8).collect();
+        let (events, listener) = recorder();
+        let upload = Upload::new(UploadBody::from_bytes(data.clone())).with_listener(listener);
+        assert_eq!(upload.framing_header(), ("Content-Length", data.len().to_string()));
+        let mut sent = Vec::new();
+        assert_eq!(upload.write_http1(&mut sent).unwrap(), data.len() as u64);
+        assert_eq!(sent, data);
+        
+        let kinds: Vec<_> = events.lock().unwrap().iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, [UploadEventKind::LoadStart, UploadEventKind::Progress, UploadEventKind::Load, UploadEventKind::LoadEnd]);
+        assert_eq!(events.lock().unwrap()[2].loaded, data.len() as u64);
+        
+        // Unknown length goes out chunked
+        let stream = Upload::new(UploadBody::new(|| -> io::Result<Box<dyn Read + Send>> {
+            Ok(Box::new(io::Cursor::new(b"hello".to_vec())))
+        }));
+        assert_eq!(stream.framing_header(), ("Transfer-Encoding", "chunked".to_string()));
+        let mut sent = Vec::new();
+        stream.write_http1(&mut sent).unwrap();
+        assert_eq!(sent, b"5\r\nhello\r\n0\r\n\r\n");
+        
+        // File ranges read just their bytes
+        let path = std::env::temp_dir().join(format!("fos-upload-test-{}", std::process::id()));
+        std::fs::write(&path, b"0123456789").unwrap();
+        let slice = UploadBody::new(FileSource::new(&path).unwrap().slice(2, 6));
+        let mut sent = Vec::new();
+        Upload::new(slice).write_http1(&mut sent).unwrap();
+        assert_eq!(sent, b"2345");
+        let _ = std::fs::remove_file(path);
+    }
+    
+    #[test]
+    fn test_upload_pause_and_abort() {
+        let upload = Upload::new(UploadBody::from_bytes(vec![7; 1000]));
+        let control = upload.control();
+        control.pause();
+        let resumer = std::thread::spawn({
+            let control = control.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(20));
+                assert!(control.is_paused());
+                control.resume();
+            }
+        });
+        let mut sent = Vec::new();
+        assert_eq!(upload.write_http1(&mut sent).unwrap(), 1000);
+        resumer.join().unwrap();
+        
+        let (events, listener) = recorder();
+        let upload = Upload::new(UploadBody::from_bytes(vec![7; 1000])).with_listener(listener);
+        upload.control().abort();
+        assert!(matches!(upload.write_http1(&mut Vec::new()), Err(UploadError::Aborted)));
+        let kinds: Vec<_> = events.lock().unwrap().iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, [UploadEventKind::LoadStart, UploadEventKind::Abort, UploadEventKind::LoadEnd]);
+    }
+}
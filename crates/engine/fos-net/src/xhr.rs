@@ -4,6 +4,9 @@
 //! Modern code should use fetch() instead.
 
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::upload::{Upload, UploadBody, UploadControl, UploadProgress};
 
 /// XMLHttpRequest ready states
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -41,6 +44,9 @@ pub struct XmlHttpRequest {
     request_headers: HashMap<String, String>,
     send_flag: bool,
     error_flag: bool,
+    upload_control: UploadControl,
+    /// Upload events not yet dispatched to `xhr.upload` listeners
+    upload_events: Arc<Mutex<Vec<UploadProgress>>>,
 }
 
 /// Response types
@@ -68,6 +74,8 @@ impl XmlHttpRequest {
         self.ready_state = ReadyState::Opened;
         self.send_flag = false;
         self.request_headers.clear();
+        self.upload_control = UploadControl::new();
+        self.upload_events.lock().unwrap().clear();
     }
     
     /// Set request header
@@ -156,25 +164,64 @@ impl XmlHttpRequest {
         
         self.send_flag = true;
         
-        // Build request using custom client
-        let mut client = crate::client::HttpClient::new();
-        
-        // Convert headers to expected format
-        let headers: Vec<(String, String)> = self.request_headers.iter()
-            .filter(|(name, _)| !name.starts_with('_'))
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
-        
+        let headers = self.outgoing_headers();
         self.ready_state = ReadyState::HeadersReceived;
         
         // Send request using custom client
-        let result = client.request(
+        let result = crate::client::HttpClient::new().request(
             &self.method,
             &self.url,
             Some(headers),
             body.map(|b| b.into_bytes()),
         );
+        self.finish(result)
+    }
+    
+    /// Send a body streamed from a blob or file, queueing upload progress
+    /// events as it goes out. Pause or abort it through `upload_control()`.
+    pub fn send_upload(&mut self, body: UploadBody) -> Result<(), XhrError> {
+        if self.ready_state != ReadyState::Opened || self.send_flag {
+            return Err(XhrError::InvalidState);
+        }
+        
+        self.send_flag = true;
+        
+        let events = self.upload_events.clone();
+        let upload = Upload::new(body)
+            .with_control(self.upload_control.clone())
+            .with_listener(move |progress| events.lock().unwrap().push(*progress));
         
+        let headers = self.outgoing_headers();
+        let result = crate::client::HttpClient::new().upload(&self.method, &self.url, Some(headers), upload);
+        if self.upload_control.is_aborted() {
+            self.abort();
+            return Err(XhrError::Aborted);
+        }
+        
+        self.ready_state = ReadyState::HeadersReceived;
+        self.finish(result)
+    }
+    
+    /// Controls pausing and aborting the body being uploaded
+    pub fn upload_control(&self) -> UploadControl {
+        self.upload_control.clone()
+    }
+    
+    /// Take the upload events fired since the last call
+    pub fn take_upload_events(&mut self) -> Vec<UploadProgress> {
+        std::mem::take(&mut *self.upload_events.lock().unwrap())
+    }
+    
+    /// Request headers to send, without internal entries
+    fn outgoing_headers(&self) -> Vec<(String, String)> {
+        self.request_headers.iter()
+            .filter(|(name, _)| !name.starts_with('_'))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+    
+    /// Take in the client's response
+    fn finish(&mut self, result: Result<crate::Response, crate::NetError>) -> Result<(), XhrError> {
         match result {
             Ok(response) => {
                 self.status = response.status;
@@ -229,6 +276,7 @@ impl XmlHttpRequest {
         self.ready_state = ReadyState::Done;
         self.send_flag = false;
         self.error_flag = true;
+        self.upload_control.abort();
         
         // Clear response
         self.status = 0;
@@ -273,6 +321,9 @@ pub enum XhrError {
     #[error("Request timeout")]
     Timeout,
     
+    #[error("Request aborted")]
+    Aborted,
+    
     #[error("Parse error: {0}")]
     ParseError(String),
 }
@@ -406,6 +457,18 @@ mod tests {
         assert!(xhr.set_request_header("Cookie", "value").is_err());
     }
     
+    #[test]
+    fn test_xhr_open_resets_upload() {
+        let mut xhr = XmlHttpRequest::new();
+        xhr.open("POST", "https://example.com", true);
+        xhr.upload_control().abort();
+        assert!(xhr.upload_control().is_aborted());
+        
+        xhr.open("POST", "https://example.com", true);
+        assert!(!xhr.upload_control().is_aborted());
+        assert!(xhr.take_upload_events().is_empty());
+    }
+    
     #[test]
     fn test_form_data() {
         let mut form = FormData::new();
@@ -0,0 +1,32 @@
+//! Cookie jars across public suffix list updates
+//!
+//! Installing a list changes it for the whole process, so this runs in its
+//! own test binary rather than next to the cookie unit tests.
+
+use fos_dom::url::PublicSuffixList;
+use fos_net::{Cookie, CookieJar};
+
+#[test]
+fn test_lookup_after_suffix_list_update() {
+    let mut jar = CookieJar::new();
+    jar.add(Cookie::new("a", "1", "shop.host.fostest", "/"));
+
+    // Make host.fostest a public suffix, moving the cookie to the
+    // shop.host.fostest bucket
+    let mut list = PublicSuffixList::builtin().clone();
+    list.add_rules("host.fostest");
+    PublicSuffixList::install(list);
+    assert_eq!(jar.get_cookies("shop.host.fostest", "/", false).len(), 1);
+    jar.add(Cookie::new("b", "2", "shop.host.fostest", "/"));
+    assert_eq!(jar.get_cookies("shop.host.fostest", "/", false).len(), 2);
+    jar.clear_domain("shop.host.fostest");
+    assert!(jar.is_empty());
+
+    // A jar made after the update keys with the new list
+    let mut jar = CookieJar::new();
+    jar.add(Cookie::new("c", "3", "shop.host.fostest", "/"));
+    jar.add(Cookie::new("d", "4", "cart.host.fostest", "/"));
+    jar.clear_domain("shop.host.fostest");
+    assert_eq!(jar.len(), 1);
+    PublicSuffixList::reset();
+}
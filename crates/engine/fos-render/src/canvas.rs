@@ -1,6 +1,8 @@
 //! Canvas - pixel buffer backed by tiny-skia
 //!
-//! Provides a drawing surface for rendering layout boxes.
+//! Provides a drawing surface for rendering layout boxes. Drawing
+//! coordinates are CSS pixels, scaled to device pixels by the canvas scale
+//! (device pixel ratio times page zoom); pixel access is in device pixels.
 
 use tiny_skia::{Pixmap, Paint, PathBuilder, Stroke, Transform, FillRule, LineCap, LineJoin, Rect as SkiaRect};
 use crate::Color;
@@ -8,12 +10,34 @@ use crate::Color;
 /// Pixel canvas backed by tiny-skia Pixmap
 pub struct Canvas {
     pixmap: Pixmap,
+    /// Device pixels per CSS pixel
+    scale: f32,
 }
 
 impl Canvas {
     /// Create a new canvas with given dimensions
     pub fn new(width: u32, height: u32) -> Option<Self> {
-        Pixmap::new(width, height).map(|pixmap| Self { pixmap })
+        Pixmap::new(width, height).map(|pixmap| Self { pixmap, scale: 1.0 })
+    }
+    
+    /// Scale drawing by `scale` device pixels per CSS pixel
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = if scale > 0.0 { scale } else { 1.0 };
+    }
+    
+    /// Device pixels per CSS pixel
+    #[inline]
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+    
+    /// Canvas size in CSS pixels
+    pub fn css_size(&self) -> (f32, f32) {
+        (self.width() as f32 / self.scale, self.height() as f32 / self.scale)
+    }
+    
+    fn transform(&self) -> Transform {
+        Transform::from_scale(self.scale, self.scale)
     }
     
     /// Get canvas width
@@ -54,7 +78,7 @@ impl Canvas {
         paint.set_color(to_skia_color(color));
         paint.anti_alias = true;
         
-        self.pixmap.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), None);
+        self.pixmap.fill_path(&path, &paint, FillRule::Winding, self.transform(), None);
     }
     
     /// Fill a rounded rectangle
@@ -110,7 +134,7 @@ impl Canvas {
         paint.set_color(to_skia_color(color));
         paint.anti_alias = true;
         
-        self.pixmap.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), None);
+        self.pixmap.fill_path(&path, &paint, FillRule::Winding, self.transform(), None);
     }
     
     /// Stroke a rectangle (draw border)
@@ -148,7 +172,7 @@ impl Canvas {
             dash: None,
         };
         
-        self.pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+        self.pixmap.stroke_path(&path, &paint, &stroke, self.transform(), None);
     }
     
     /// Draw a line
@@ -181,7 +205,7 @@ impl Canvas {
             dash: None,
         };
         
-        self.pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+        self.pixmap.stroke_path(&path, &paint, &stroke, self.transform(), None);
     }
     
     /// Get pixel at position
@@ -284,6 +308,19 @@ mod tests {
         assert_eq!(pixel.b, 0);
     }
     
+    #[test]
+    fn test_scaled_drawing() {
+        let mut canvas = Canvas::new(100, 100).unwrap();
+        canvas.set_scale(2.0);
+        assert_eq!(canvas.css_size(), (50.0, 50.0));
+        canvas.clear(Color::WHITE);
+        canvas.fill_rect(10.0, 10.0, 10.0, 10.0, Color::rgb(255, 0, 0));
+        
+        // Device pixels 20..40 are filled
+        assert_eq!(canvas.get_pixel(35, 35).unwrap().g, 0);
+        assert_eq!(canvas.get_pixel(15, 15).unwrap().g, 255);
+    }
+    
     #[test]
     fn test_rounded_rect() {
         let mut canvas = Canvas::new(100, 100).unwrap();
@@ -0,0 +1,271 @@
+//! Form control painting
+//!
+//! Native look of checkboxes, radios, text fields, buttons, selects, range
+//! sliders and progress bars. Colors come from a [`FormControlTheme`] picked
+//! by the element's used color scheme, so dark pages get dark fields instead
+//! of white ones, and `accent-color` tints the checked and filled parts.
+//! Controls with `appearance: none` are not painted here at all.
+
+use crate::{Canvas, Color};
+use fos_layout::Rect;
+
+/// Kind of native widget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormControlKind {
+    Checkbox,
+    Radio,
+    TextField,
+    Button,
+    Select,
+    Range,
+    Progress,
+}
+
+impl FormControlKind {
+    /// Widget for an element, from its tag and `type` attribute
+    pub fn for_element(tag: &str, input_type: Option<&str>) -> Option<Self> {
+        Some(match tag {
+            "input" => match input_type.unwrap_or("text").to_ascii_lowercase().as_str() {
+                "checkbox" => Self::Checkbox,
+                "radio" => Self::Radio,
+                "range" => Self::Range,
+                "button" | "submit" | "reset" => Self::Button,
+                "hidden" | "image" | "file" | "color" => return None,
+                _ => Self::TextField,
+            },
+            "textarea" => Self::TextField,
+            "button" => Self::Button,
+            "select" => Self::Select,
+            "progress" | "meter" => Self::Progress,
+            _ => return None,
+        })
+    }
+    
+    /// Default size at a font size, before author sizing
+    pub fn default_size(&self, font_size: f32) -> (f32, f32) {
+        let line = (font_size * 1.6).round();
+        match self {
+            Self::Checkbox | Self::Radio => (13.0, 13.0),
+            Self::TextField => ((font_size * 11.0).round(), line),
+            Self::Button | Self::Select => ((font_size * 5.0).round(), line),
+            Self::Range => (129.0, 16.0),
+            Self::Progress => (160.0, 16.0),
+        }
+    }
+}
+
+/// Dynamic state of a control
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FormControlState {
+    pub checked: bool,
+    pub disabled: bool,
+    pub focused: bool,
+    /// Thumb position of a range or fill of a progress bar, 0 to 1
+    pub value: f32,
+}
+
+/// Colors of native controls for one color scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormControlTheme {
+    /// Page background behind the controls
+    pub canvas: Color,
+    pub canvas_text: Color,
+    /// Text field and unchecked box fill
+    pub field: Color,
+    pub field_text: Color,
+    pub border: Color,
+    pub button_face: Color,
+    pub button_text: Color,
+    /// Unfilled part of range and progress tracks
+    pub track: Color,
+    /// Checked, filled and focused parts
+    pub accent: Color,
+    /// Checkmarks drawn on top of the accent
+    pub accent_contrast: Color,
+}
+
+impl FormControlTheme {
+    pub const LIGHT: FormControlTheme = FormControlTheme {
+        canvas: Color::WHITE,
+        canvas_text: Color::BLACK,
+        field: Color::WHITE,
+        field_text: Color::BLACK,
+        border: Color::rgb(118, 118, 118),
+        button_face: Color::rgb(239, 239, 239),
+        button_text: Color::BLACK,
+        track: Color::rgb(239, 239, 239),
+        accent: Color::rgb(0, 117, 255),
+        accent_contrast: Color::WHITE,
+    };
+    
+    pub const DARK: FormControlTheme = FormControlTheme {
+        canvas: Color::rgb(18, 18, 18),
+        canvas_text: Color::WHITE,
+        field: Color::rgb(59, 59, 59),
+        field_text: Color::WHITE,
+        border: Color::rgb(133, 133, 133),
+        button_face: Color::rgb(107, 107, 107),
+        button_text: Color::WHITE,
+        track: Color::rgb(59, 59, 59),
+        accent: Color::rgb(153, 200, 255),
+        accent_contrast: Color::BLACK,
+    };
+    
+    /// Theme of the used color scheme
+    pub fn for_scheme(dark: bool) -> Self {
+        if dark { Self::DARK } else { Self::LIGHT }
+    }
+    
+    /// Replace the platform accent by an author `accent-color`
+    pub fn with_accent(mut self, accent: Color) -> Self {
+        self.accent = Color::rgb(accent.r, accent.g, accent.b);
+        self.accent_contrast = contrast_color(accent);
+        self
+    }
+}
+
+impl Default for FormControlTheme {
+    fn default() -> Self {
+        Self::LIGHT
+    }
+}
+
+/// Black or white, whichever reads better on `background`
+fn contrast_color(background: Color) -> Color {
+    let luma = 0.2126 * background.r as f32 + 0.7152 * background.g as f32 + 0.0722 * background.b as f32;
+    if luma > 150.0 { Color::BLACK } else { Color::WHITE }
+}
+
+/// Blend `color` halfway into `base`, for disabled controls
+fn faded(color: Color, base: Color) -> Color {
+    let mix = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+    Color::rgb(mix(color.r, base.r), mix(color.g, base.g), mix(color.b, base.b))
+}
+
+/// Paint a control filling `rect`
+pub fn paint_form_control(
+    canvas: &mut Canvas,
+    kind: FormControlKind,
+    rect: &Rect,
+    state: &FormControlState,
+    theme: &FormControlTheme,
+) {
+    let theme = if state.disabled {
+        let fade = |c| faded(c, theme.canvas);
+        FormControlTheme {
+            field: fade(theme.field),
+            field_text: fade(theme.field_text),
+            border: fade(theme.border),
+            button_face: fade(theme.button_face),
+            button_text: fade(theme.button_text),
+            track: fade(theme.track),
+            accent: fade(theme.accent),
+            accent_contrast: fade(theme.accent_contrast),
+            ..*theme
+        }
+    } else {
+        *theme
+    };
+    let Rect { x, y, width, height } = *rect;
+    
+    match kind {
+        FormControlKind::Checkbox => {
+            if state.checked {
+                canvas.fill_rounded_rect(x, y, width, height, 2.0, theme.accent);
+                let stroke = (width / 8.0).max(1.5);
+                canvas.draw_line(x + width * 0.22, y + height * 0.52, x + width * 0.42, y + height * 0.72, stroke, theme.accent_contrast);
+                canvas.draw_line(x + width * 0.42, y + height * 0.72, x + width * 0.78, y + height * 0.3, stroke, theme.accent_contrast);
+            } else {
+                bordered(canvas, rect, 2.0, 1.0, theme.border, theme.field);
+            }
+        }
+        FormControlKind::Radio => {
+            let radius = width.min(height) / 2.0;
+            if state.checked {
+                bordered(canvas, rect, radius, 1.0, theme.accent, theme.field);
+                let inset = width.min(height) * 0.25;
+                canvas.fill_rounded_rect(x + inset, y + inset, width - inset * 2.0, height - inset * 2.0, radius, theme.accent);
+            } else {
+                bordered(canvas, rect, radius, 1.0, theme.border, theme.field);
+            }
+        }
+        FormControlKind::TextField => {
+            if state.focused {
+                bordered(canvas, rect, 2.0, 2.0, theme.accent, theme.field);
+            } else {
+                bordered(canvas, rect, 2.0, 1.0, theme.border, theme.field);
+            }
+        }
+        FormControlKind::Button => {
+            let border = if state.focused { theme.accent } else { theme.border };
+            bordered(canvas, rect, 3.0, 1.0, border, theme.button_face);
+        }
+        FormControlKind::Select => {
+            let border = if state.focused { theme.accent } else { theme.border };
+            bordered(canvas, rect, 2.0, 1.0, border, theme.field);
+            // Drop-down chevron
+            let cx = x + width - 10.0;
+            let cy = y + height / 2.0;
+            canvas.draw_line(cx - 4.0, cy - 2.0, cx, cy + 2.0, 1.5, theme.field_text);
+            canvas.draw_line(cx, cy + 2.0, cx + 4.0, cy - 2.0, 1.5, theme.field_text);
+        }
+        FormControlKind::Range => {
+            let value = state.value.clamp(0.0, 1.0);
+            let thumb = height.min(16.0);
+            let track_y = y + (height - 4.0) / 2.0;
+            let thumb_x = x + value * (width - thumb);
+            canvas.fill_rounded_rect(x, track_y, width, 4.0, 2.0, theme.track);
+            canvas.fill_rounded_rect(x, track_y, thumb_x - x + thumb / 2.0, 4.0, 2.0, theme.accent);
+            canvas.fill_rounded_rect(thumb_x, y + (height - thumb) / 2.0, thumb, thumb, thumb / 2.0, theme.accent);
+        }
+        FormControlKind::Progress => {
+            let value = state.value.clamp(0.0, 1.0);
+            let radius = height / 2.0;
+            bordered(canvas, rect, radius, 1.0, theme.border, theme.track);
+            canvas.fill_rounded_rect(x, y, width * value, height, radius, theme.accent);
+        }
+    }
+}
+
+/// Rounded box with a `border`-wide edge around a `fill` interior
+fn bordered(canvas: &mut Canvas, rect: &Rect, radius: f32, border: f32, edge: Color, fill: Color) {
+    canvas.fill_rounded_rect(rect.x, rect.y, rect.width, rect.height, radius, edge);
+    canvas.fill_rounded_rect(
+        rect.x + border,
+        rect.y + border,
+        rect.width - border * 2.0,
+        rect.height - border * 2.0,
+        (radius - border).max(0.0),
+        fill,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_dark_scheme_and_accent() {
+        let mut canvas = Canvas::new(60, 30).unwrap();
+        let field = Rect::new(0.0, 0.0, 40.0, 20.0);
+        let dark = FormControlTheme::for_scheme(true);
+        paint_form_control(&mut canvas, FormControlKind::TextField, &field, &FormControlState::default(), &dark);
+        assert_eq!(canvas.get_pixel(20, 10), Some(dark.field));
+        
+        let theme = FormControlTheme::LIGHT.with_accent(Color::rgb(255, 200, 0));
+        assert_eq!(theme.accent_contrast, Color::BLACK);
+        let checkbox = Rect::new(44.0, 0.0, 13.0, 13.0);
+        let checked = FormControlState { checked: true, ..Default::default() };
+        paint_form_control(&mut canvas, FormControlKind::Checkbox, &checkbox, &checked, &theme);
+        assert_eq!(canvas.get_pixel(46, 11), Some(theme.accent));
+    }
+    
+    #[test]
+    fn test_control_for_element() {
+        assert_eq!(FormControlKind::for_element("input", None), Some(FormControlKind::TextField));
+        assert_eq!(FormControlKind::for_element("input", Some("Checkbox")), Some(FormControlKind::Checkbox));
+        assert_eq!(FormControlKind::for_element("input", Some("hidden")), None);
+        assert_eq!(FormControlKind::for_element("select", None), Some(FormControlKind::Select));
+        assert_eq!(FormControlKind::for_element("div", None), None);
+    }
+}
@@ -0,0 +1,1717 @@
+//! Software GL Device
+//!
+//! An OpenGL ES-style device that executes draw calls: buffer and texture
+//! uploads, framebuffer objects, translated GLSL ES programs, clipping,
+//! perspective-correct triangle, line and point rasterization, depth testing
+//! and blending. Color buffers use GL's bottom-left origin; `frame()` flips
+//! the default framebuffer top-down for compositing into a page layer.
+
+use std::collections::HashMap;
+
+use super::glsl::{Executor, Qualifier, ShaderModule, Stage, TextureSampler, Type, Value};
+use super::GpuError;
+
+/// Number of texture units
+pub const MAX_TEXTURE_UNITS: usize = 16;
+/// Number of vertex attribute slots
+pub const MAX_VERTEX_ATTRIBS: usize = 16;
+
+/// Primitive topology
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+    Points,
+    Lines,
+    LineLoop,
+    LineStrip,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl Primitive {
+    pub fn from_gl(mode: u32) -> Option<Self> {
+        Some(match mode {
+            0x0000 => Self::Points,
+            0x0001 => Self::Lines,
+            0x0002 => Self::LineLoop,
+            0x0003 => Self::LineStrip,
+            0x0004 => Self::Triangles,
+            0x0005 => Self::TriangleStrip,
+            0x0006 => Self::TriangleFan,
+            _ => return None,
+        })
+    }
+}
+
+/// Vertex attribute and index component type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentType {
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    UnsignedInt,
+    Float,
+}
+
+impl ComponentType {
+    pub fn from_gl(ty: u32) -> Option<Self> {
+        Some(match ty {
+            0x1400 => Self::Byte,
+            0x1401 => Self::UnsignedByte,
+            0x1402 => Self::Short,
+            0x1403 => Self::UnsignedShort,
+            0x1405 => Self::UnsignedInt,
+            0x1406 => Self::Float,
+            _ => return None,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Byte | Self::UnsignedByte => 1,
+            Self::Short | Self::UnsignedShort => 2,
+            Self::UnsignedInt | Self::Float => 4,
+        }
+    }
+
+    /// Read one component; `normalized` maps integers into [0, 1] or [-1, 1]
+    fn read(&self, bytes: &[u8], normalized: bool) -> f32 {
+        let (value, max) = match self {
+            Self::Byte => (bytes[0] as i8 as f32, 127.0),
+            Self::UnsignedByte => (bytes[0] as f32, 255.0),
+            Self::Short => (i16::from_le_bytes([bytes[0], bytes[1]]) as f32, 32767.0),
+            Self::UnsignedShort => (u16::from_le_bytes([bytes[0], bytes[1]]) as f32, 65535.0),
+            Self::UnsignedInt => (u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32, u32::MAX as f32),
+            Self::Float => return f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        };
+        if normalized {
+            (value / max).max(-1.0)
+        } else {
+            value
+        }
+    }
+}
+
+/// Source pixel layout for texture uploads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Alpha,
+    Rgb,
+    Rgba,
+    Luminance,
+    LuminanceAlpha,
+}
+
+impl PixelFormat {
+    pub fn from_gl(format: u32) -> Option<Self> {
+        Some(match format {
+            0x1906 => Self::Alpha,
+            0x1907 => Self::Rgb,
+            0x1908 => Self::Rgba,
+            0x1909 => Self::Luminance,
+            0x190A => Self::LuminanceAlpha,
+            _ => return None,
+        })
+    }
+
+    pub fn channels(&self) -> usize {
+        match self {
+            Self::Alpha | Self::Luminance => 1,
+            Self::LuminanceAlpha => 2,
+            Self::Rgb => 3,
+            Self::Rgba => 4,
+        }
+    }
+
+    fn to_rgba(self, src: &[u8]) -> [u8; 4] {
+        match self {
+            Self::Alpha => [0, 0, 0, src[0]],
+            Self::Luminance => [src[0], src[0], src[0], 255],
+            Self::LuminanceAlpha => [src[0], src[0], src[0], src[1]],
+            Self::Rgb => [src[0], src[1], src[2], 255],
+            Self::Rgba => [src[0], src[1], src[2], src[3]],
+        }
+    }
+}
+
+/// Texture filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+    NearestMipmapNearest,
+    LinearMipmapNearest,
+    NearestMipmapLinear,
+    LinearMipmapLinear,
+}
+
+impl Filter {
+    pub fn from_gl(filter: u32) -> Option<Self> {
+        Some(match filter {
+            0x2600 => Self::Nearest,
+            0x2601 => Self::Linear,
+            0x2700 => Self::NearestMipmapNearest,
+            0x2701 => Self::LinearMipmapNearest,
+            0x2702 => Self::NearestMipmapLinear,
+            0x2703 => Self::LinearMipmapLinear,
+            _ => return None,
+        })
+    }
+
+    fn uses_mipmaps(&self) -> bool {
+        !matches!(self, Self::Nearest | Self::Linear)
+    }
+
+    fn is_linear(&self) -> bool {
+        matches!(self, Self::Linear | Self::LinearMipmapNearest | Self::LinearMipmapLinear)
+    }
+}
+
+/// Texture coordinate wrapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat,
+    ClampToEdge,
+    MirroredRepeat,
+}
+
+impl Wrap {
+    pub fn from_gl(wrap: u32) -> Option<Self> {
+        Some(match wrap {
+            0x2901 => Self::Repeat,
+            0x812F => Self::ClampToEdge,
+            0x8370 => Self::MirroredRepeat,
+            _ => return None,
+        })
+    }
+
+    fn apply(&self, i: i32, size: i32) -> i32 {
+        match self {
+            Self::Repeat => i.rem_euclid(size),
+            Self::ClampToEdge => i.clamp(0, size - 1),
+            Self::MirroredRepeat => {
+                let period = i.rem_euclid(size * 2);
+                if period < size { period } else { size * 2 - 1 - period }
+            }
+        }
+    }
+}
+
+/// Texture parameter update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TexParam {
+    MinFilter(Filter),
+    MagFilter(Filter),
+    WrapS(Wrap),
+    WrapT(Wrap),
+}
+
+/// Depth comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareFunc {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunc {
+    pub fn from_gl(func: u32) -> Option<Self> {
+        Some(match func {
+            0x0200 => Self::Never,
+            0x0201 => Self::Less,
+            0x0202 => Self::Equal,
+            0x0203 => Self::LessEqual,
+            0x0204 => Self::Greater,
+            0x0205 => Self::NotEqual,
+            0x0206 => Self::GreaterEqual,
+            0x0207 => Self::Always,
+            _ => return None,
+        })
+    }
+
+    fn test(&self, incoming: f32, stored: f32) -> bool {
+        match self {
+            Self::Never => false,
+            Self::Less => incoming < stored,
+            Self::Equal => incoming == stored,
+            Self::LessEqual => incoming <= stored,
+            Self::Greater => incoming > stored,
+            Self::NotEqual => incoming != stored,
+            Self::GreaterEqual => incoming >= stored,
+            Self::Always => true,
+        }
+    }
+}
+
+/// Blend factor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+    ConstantColor,
+    OneMinusConstantColor,
+    ConstantAlpha,
+    OneMinusConstantAlpha,
+    SrcAlphaSaturate,
+}
+
+impl BlendFactor {
+    pub fn from_gl(factor: u32) -> Option<Self> {
+        Some(match factor {
+            0 => Self::Zero,
+            1 => Self::One,
+            0x0300 => Self::SrcColor,
+            0x0301 => Self::OneMinusSrcColor,
+            0x0302 => Self::SrcAlpha,
+            0x0303 => Self::OneMinusSrcAlpha,
+            0x0304 => Self::DstAlpha,
+            0x0305 => Self::OneMinusDstAlpha,
+            0x0306 => Self::DstColor,
+            0x0307 => Self::OneMinusDstColor,
+            0x0308 => Self::SrcAlphaSaturate,
+            0x8001 => Self::ConstantColor,
+            0x8002 => Self::OneMinusConstantColor,
+            0x8003 => Self::ConstantAlpha,
+            0x8004 => Self::OneMinusConstantAlpha,
+            _ => return None,
+        })
+    }
+
+    fn weight(&self, src: [f32; 4], dst: [f32; 4], constant: [f32; 4], channel: usize) -> f32 {
+        match self {
+            Self::Zero => 0.0,
+            Self::One => 1.0,
+            Self::SrcColor => src[channel],
+            Self::OneMinusSrcColor => 1.0 - src[channel],
+            Self::DstColor => dst[channel],
+            Self::OneMinusDstColor => 1.0 - dst[channel],
+            Self::SrcAlpha => src[3],
+            Self::OneMinusSrcAlpha => 1.0 - src[3],
+            Self::DstAlpha => dst[3],
+            Self::OneMinusDstAlpha => 1.0 - dst[3],
+            Self::ConstantColor => constant[channel],
+            Self::OneMinusConstantColor => 1.0 - constant[channel],
+            Self::ConstantAlpha => constant[3],
+            Self::OneMinusConstantAlpha => 1.0 - constant[3],
+            Self::SrcAlphaSaturate if channel == 3 => 1.0,
+            Self::SrcAlphaSaturate => src[3].min(1.0 - dst[3]),
+        }
+    }
+}
+
+/// Blend equation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendEquation {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl BlendEquation {
+    pub fn from_gl(mode: u32) -> Option<Self> {
+        Some(match mode {
+            0x8006 => Self::Add,
+            0x800A => Self::Subtract,
+            0x800B => Self::ReverseSubtract,
+            0x8007 => Self::Min,
+            0x8008 => Self::Max,
+            _ => return None,
+        })
+    }
+}
+
+/// Blend configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlendState {
+    pub src_rgb: BlendFactor,
+    pub dst_rgb: BlendFactor,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+    pub equation_rgb: BlendEquation,
+    pub equation_alpha: BlendEquation,
+    pub color: [f32; 4],
+}
+
+impl Default for BlendState {
+    fn default() -> Self {
+        Self {
+            src_rgb: BlendFactor::One,
+            dst_rgb: BlendFactor::Zero,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::Zero,
+            equation_rgb: BlendEquation::Add,
+            equation_alpha: BlendEquation::Add,
+            color: [0.0; 4],
+        }
+    }
+}
+
+impl BlendState {
+    fn blend(&self, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+        std::array::from_fn(|c| {
+            let (sf, df, eq) = if c == 3 {
+                (self.src_alpha, self.dst_alpha, self.equation_alpha)
+            } else {
+                (self.src_rgb, self.dst_rgb, self.equation_rgb)
+            };
+            let s = src[c] * sf.weight(src, dst, self.color, c);
+            let d = dst[c] * df.weight(src, dst, self.color, c);
+            let out = match eq {
+                BlendEquation::Add => s + d,
+                BlendEquation::Subtract => s - d,
+                BlendEquation::ReverseSubtract => d - s,
+                BlendEquation::Min => src[c].min(dst[c]),
+                BlendEquation::Max => src[c].max(dst[c]),
+            };
+            out.clamp(0.0, 1.0)
+        })
+    }
+}
+
+/// Faces to cull
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullFace {
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+impl CullFace {
+    pub fn from_gl(mode: u32) -> Option<Self> {
+        Some(match mode {
+            0x0404 => Self::Front,
+            0x0405 => Self::Back,
+            0x0408 => Self::FrontAndBack,
+            _ => return None,
+        })
+    }
+}
+
+/// Vertex attribute source
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttribPointer {
+    pub buffer: u32,
+    /// Components per vertex, 1 to 4
+    pub size: usize,
+    pub component: ComponentType,
+    pub normalized: bool,
+    /// Byte stride; 0 means tightly packed
+    pub stride: usize,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone)]
+struct AttribSlot {
+    pointer: Option<AttribPointer>,
+    enabled: bool,
+    divisor: u32,
+    /// Value used while the array is disabled
+    constant: [f32; 4],
+}
+
+impl Default for AttribSlot {
+    fn default() -> Self {
+        Self { pointer: None, enabled: false, divisor: 0, constant: [0.0, 0.0, 0.0, 1.0] }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Texture {
+    width: usize,
+    height: usize,
+    /// RGBA8, row 0 at t = 0
+    data: Vec<u8>,
+    min_filter: Filter,
+    mag_filter: Filter,
+    wrap_s: Wrap,
+    wrap_t: Wrap,
+    has_mipmaps: bool,
+}
+
+impl Texture {
+    fn is_complete(&self) -> bool {
+        !self.data.is_empty() && (self.has_mipmaps || !self.min_filter.uses_mipmaps())
+    }
+
+    fn texel(&self, x: i32, y: i32) -> [f32; 4] {
+        let x = self.wrap_s.apply(x, self.width as i32) as usize;
+        let y = self.wrap_t.apply(y, self.height as i32) as usize;
+        let i = (y * self.width + x) * 4;
+        std::array::from_fn(|c| self.data[i + c] as f32 / 255.0)
+    }
+
+    fn sample(&self, s: f32, t: f32) -> [f32; 4] {
+        let u = s * self.width as f32;
+        let v = t * self.height as f32;
+        if !self.mag_filter.is_linear() {
+            return self.texel(u.floor() as i32, v.floor() as i32);
+        }
+        let (u, v) = (u - 0.5, v - 0.5);
+        let (x0, y0) = (u.floor() as i32, v.floor() as i32);
+        let (fx, fy) = (u - u.floor(), v - v.floor());
+        let (a, b) = (self.texel(x0, y0), self.texel(x0 + 1, y0));
+        let (c, d) = (self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1));
+        std::array::from_fn(|i| {
+            let top = a[i] + (b[i] - a[i]) * fx;
+            let bottom = c[i] + (d[i] - c[i]) * fx;
+            top + (bottom - top) * fy
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Renderbuffer {
+    width: usize,
+    height: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Framebuffer {
+    color: Option<u32>,
+    depth: Option<u32>,
+    /// Depth storage for the attached renderbuffer
+    depth_data: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+struct Program {
+    vertex: ShaderModule,
+    fragment: ShaderModule,
+    attributes: Vec<(String, Type, u32)>,
+    varyings: Vec<(String, Type)>,
+    uniforms: HashMap<String, (Type, Option<usize>)>,
+    values: HashMap<String, Value>,
+    output: String,
+}
+
+/// Active attribute or uniform description
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveInfo {
+    pub name: String,
+    pub ty: Type,
+    pub size: usize,
+}
+
+/// Software GL device
+#[derive(Debug)]
+pub struct GlDevice {
+    width: usize,
+    height: usize,
+    color: Vec<u8>,
+    depth: Vec<f32>,
+    next_id: u32,
+    buffers: HashMap<u32, Vec<u8>>,
+    textures: HashMap<u32, Texture>,
+    renderbuffers: HashMap<u32, Renderbuffer>,
+    framebuffers: HashMap<u32, Framebuffer>,
+    programs: HashMap<u32, Program>,
+    texture_units: [Option<u32>; MAX_TEXTURE_UNITS],
+    attribs: Vec<AttribSlot>,
+    element_buffer: Option<u32>,
+    framebuffer: Option<u32>,
+    program: Option<u32>,
+    pub viewport: (i32, i32, i32, i32),
+    pub scissor: Option<(i32, i32, i32, i32)>,
+    pub clear_color: [f32; 4],
+    pub clear_depth: f32,
+    pub blend: Option<BlendState>,
+    pub depth_func: Option<CompareFunc>,
+    pub depth_mask: bool,
+    pub color_mask: [bool; 4],
+    pub cull_face: Option<CullFace>,
+    /// Whether counter-clockwise triangles face front
+    pub front_ccw: bool,
+}
+
+impl GlDevice {
+    pub fn new(width: u32, height: u32) -> Self {
+        let (w, h) = (width as usize, height as usize);
+        Self {
+            width: w,
+            height: h,
+            color: vec![0; w * h * 4],
+            depth: vec![1.0; w * h],
+            next_id: 1,
+            buffers: HashMap::new(),
+            textures: HashMap::new(),
+            renderbuffers: HashMap::new(),
+            framebuffers: HashMap::new(),
+            programs: HashMap::new(),
+            texture_units: [None; MAX_TEXTURE_UNITS],
+            attribs: vec![AttribSlot::default(); MAX_VERTEX_ATTRIBS],
+            element_buffer: None,
+            framebuffer: None,
+            program: None,
+            viewport: (0, 0, width as i32, height as i32),
+            scissor: None,
+            clear_color: [0.0; 4],
+            clear_depth: 1.0,
+            blend: None,
+            depth_func: None,
+            depth_mask: true,
+            color_mask: [true; 4],
+            cull_face: None,
+            front_ccw: true,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height as u32
+    }
+
+    /// Resize and clear the default framebuffer
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width as usize;
+        self.height = height as usize;
+        self.color = vec![0; self.width * self.height * 4];
+        self.depth = vec![1.0; self.width * self.height];
+    }
+
+    fn alloc_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    // Buffers
+
+    pub fn create_buffer(&mut self) -> u32 {
+        let id = self.alloc_id();
+        self.buffers.insert(id, Vec::new());
+        id
+    }
+
+    pub fn delete_buffer(&mut self, id: u32) {
+        self.buffers.remove(&id);
+        if self.element_buffer == Some(id) {
+            self.element_buffer = None;
+        }
+    }
+
+    pub fn buffer_data(&mut self, id: u32, data: &[u8]) -> Result<(), GpuError> {
+        let buffer = self.buffers.get_mut(&id).ok_or_else(|| unknown("buffer", id))?;
+        buffer.clear();
+        buffer.extend_from_slice(data);
+        Ok(())
+    }
+
+    pub fn buffer_sub_data(&mut self, id: u32, offset: usize, data: &[u8]) -> Result<(), GpuError> {
+        let buffer = self.buffers.get_mut(&id).ok_or_else(|| unknown("buffer", id))?;
+        let end = offset.checked_add(data.len()).filter(|end| *end <= buffer.len());
+        let end = end.ok_or_else(|| GpuError::DeviceError("bufferSubData past the end of the buffer".into()))?;
+        buffer[offset..end].copy_from_slice(data);
+        Ok(())
+    }
+
+    pub fn buffer_len(&self, id: u32) -> Option<usize> {
+        self.buffers.get(&id).map(Vec::len)
+    }
+
+    /// Bind the index buffer used by `draw_elements`
+    pub fn bind_element_buffer(&mut self, id: Option<u32>) {
+        self.element_buffer = id;
+    }
+
+    // Textures
+
+    pub fn create_texture(&mut self) -> u32 {
+        let id = self.alloc_id();
+        self.textures.insert(
+            id,
+            Texture {
+                width: 0,
+                height: 0,
+                data: Vec::new(),
+                min_filter: Filter::NearestMipmapLinear,
+                mag_filter: Filter::Linear,
+                wrap_s: Wrap::Repeat,
+                wrap_t: Wrap::Repeat,
+                has_mipmaps: false,
+            },
+        );
+        id
+    }
+
+    pub fn delete_texture(&mut self, id: u32) {
+        self.textures.remove(&id);
+        for unit in self.texture_units.iter_mut().filter(|u| **u == Some(id)) {
+            *unit = None;
+        }
+    }
+
+    pub fn bind_texture(&mut self, unit: usize, id: Option<u32>) {
+        if let Some(slot) = self.texture_units.get_mut(unit) {
+            *slot = id;
+        }
+    }
+
+    pub fn texture_size(&self, id: u32) -> Option<(u32, u32)> {
+        self.textures.get(&id).map(|t| (t.width as u32, t.height as u32))
+    }
+
+    /// Allocate texture storage and optionally upload rows starting at t = 0
+    pub fn tex_image_2d(
+        &mut self,
+        id: u32,
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        data: Option<&[u8]>,
+    ) -> Result<(), GpuError> {
+        let texture = self.textures.get_mut(&id).ok_or_else(|| unknown("texture", id))?;
+        let (w, h) = (width as usize, height as usize);
+        texture.width = w;
+        texture.height = h;
+        texture.has_mipmaps = false;
+        texture.data = vec![0; w * h * 4];
+        if let Some(data) = data {
+            write_pixels(texture, 0, 0, w, h, format, data)?;
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn tex_sub_image_2d(
+        &mut self,
+        id: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        data: &[u8],
+    ) -> Result<(), GpuError> {
+        let texture = self.textures.get_mut(&id).ok_or_else(|| unknown("texture", id))?;
+        if (x + width) as usize > texture.width || (y + height) as usize > texture.height {
+            return Err(GpuError::DeviceError("texSubImage2D outside the texture".into()));
+        }
+        write_pixels(texture, x as usize, y as usize, width as usize, height as usize, format, data)
+    }
+
+    pub fn tex_parameter(&mut self, id: u32, param: TexParam) -> Result<(), GpuError> {
+        let texture = self.textures.get_mut(&id).ok_or_else(|| unknown("texture", id))?;
+        match param {
+            TexParam::MinFilter(filter) => texture.min_filter = filter,
+            TexParam::MagFilter(filter) => texture.mag_filter = filter,
+            TexParam::WrapS(wrap) => texture.wrap_s = wrap,
+            TexParam::WrapT(wrap) => texture.wrap_t = wrap,
+        }
+        Ok(())
+    }
+
+    /// Mark the mip chain as present; sampling always reads the base level
+    pub fn generate_mipmap(&mut self, id: u32) -> Result<(), GpuError> {
+        let texture = self.textures.get_mut(&id).ok_or_else(|| unknown("texture", id))?;
+        texture.has_mipmaps = !texture.data.is_empty();
+        Ok(())
+    }
+
+    // Framebuffers
+
+    pub fn create_renderbuffer(&mut self) -> u32 {
+        let id = self.alloc_id();
+        self.renderbuffers.insert(id, Renderbuffer::default());
+        id
+    }
+
+    pub fn delete_renderbuffer(&mut self, id: u32) {
+        self.renderbuffers.remove(&id);
+    }
+
+    /// Allocate depth storage for a renderbuffer
+    pub fn renderbuffer_storage(&mut self, id: u32, width: u32, height: u32) -> Result<(), GpuError> {
+        let rb = self.renderbuffers.get_mut(&id).ok_or_else(|| unknown("renderbuffer", id))?;
+        rb.width = width as usize;
+        rb.height = height as usize;
+        for fb in self.framebuffers.values_mut().filter(|fb| fb.depth == Some(id)) {
+            fb.depth_data = vec![1.0; rb.width * rb.height];
+        }
+        Ok(())
+    }
+
+    pub fn create_framebuffer(&mut self) -> u32 {
+        let id = self.alloc_id();
+        self.framebuffers.insert(id, Framebuffer::default());
+        id
+    }
+
+    pub fn delete_framebuffer(&mut self, id: u32) {
+        self.framebuffers.remove(&id);
+        if self.framebuffer == Some(id) {
+            self.framebuffer = None;
+        }
+    }
+
+    /// Bind a framebuffer object, or `None` for the default framebuffer
+    pub fn bind_framebuffer(&mut self, id: Option<u32>) {
+        self.framebuffer = id;
+    }
+
+    pub fn framebuffer_texture(&mut self, fb: u32, texture: Option<u32>) -> Result<(), GpuError> {
+        let framebuffer = self.framebuffers.get_mut(&fb).ok_or_else(|| unknown("framebuffer", fb))?;
+        framebuffer.color = texture;
+        Ok(())
+    }
+
+    pub fn framebuffer_depth(&mut self, fb: u32, renderbuffer: Option<u32>) -> Result<(), GpuError> {
+        let size = renderbuffer.and_then(|id| self.renderbuffers.get(&id)).map(|rb| rb.width * rb.height);
+        let framebuffer = self.framebuffers.get_mut(&fb).ok_or_else(|| unknown("framebuffer", fb))?;
+        framebuffer.depth = renderbuffer;
+        framebuffer.depth_data = vec![1.0; size.unwrap_or(0)];
+        Ok(())
+    }
+
+    /// Whether the bound framebuffer can be rendered to
+    pub fn framebuffer_complete(&self) -> bool {
+        let Some(id) = self.framebuffer else { return true };
+        let Some(fb) = self.framebuffers.get(&id) else { return false };
+        let Some(color) = fb.color.and_then(|t| self.textures.get(&t)) else { return false };
+        if color.data.is_empty() {
+            return false;
+        }
+        match fb.depth.and_then(|id| self.renderbuffers.get(&id)) {
+            Some(rb) => (rb.width, rb.height) == (color.width, color.height),
+            None => fb.depth.is_none(),
+        }
+    }
+
+    // Programs
+
+    /// Link translated shaders; `bindings` are explicit attribute locations
+    pub fn link_program(
+        &mut self,
+        vertex: &ShaderModule,
+        fragment: &ShaderModule,
+        bindings: &[(String, u32)],
+    ) -> Result<u32, GpuError> {
+        if vertex.stage != Stage::Vertex || fragment.stage != Stage::Fragment {
+            return Err(GpuError::ShaderError("program needs a vertex and a fragment shader".into()));
+        }
+        if vertex.version != fragment.version {
+            return Err(GpuError::ShaderError("shader versions do not match".into()));
+        }
+
+        let mut used: Vec<u32> = Vec::new();
+        let mut attributes = Vec::new();
+        let inputs: Vec<_> = vertex.globals_with(Qualifier::VertexInput).collect();
+        for input in &inputs {
+            let fixed = bindings.iter().find(|(n, _)| *n == input.name).map(|(_, l)| *l).or(input.location);
+            if let Some(location) = fixed {
+                used.push(location);
+                attributes.push((input.name.clone(), input.ty, location));
+            }
+        }
+        for input in &inputs {
+            if attributes.iter().any(|(n, _, _)| *n == input.name) {
+                continue;
+            }
+            let location = (0..).find(|l| !used.contains(l)).unwrap_or(0);
+            used.push(location);
+            attributes.push((input.name.clone(), input.ty, location));
+        }
+        if attributes.iter().any(|(_, _, l)| *l as usize >= MAX_VERTEX_ATTRIBS) {
+            return Err(GpuError::ShaderError("too many vertex attributes".into()));
+        }
+
+        let mut varyings = Vec::new();
+        for input in fragment.globals_with(Qualifier::Varying) {
+            match vertex.globals_with(Qualifier::Varying).find(|v| v.name == input.name) {
+                Some(output) if output.ty == input.ty && output.array.is_none() => {
+                    varyings.push((input.name.clone(), input.ty));
+                }
+                Some(_) => {
+                    return Err(GpuError::ShaderError(format!("varying {} differs between stages", input.name)));
+                }
+                None => {
+                    return Err(GpuError::ShaderError(format!("varying {} is not declared by the vertex shader", input.name)));
+                }
+            }
+        }
+
+        let mut uniforms = HashMap::new();
+        let mut values = HashMap::new();
+        for uniform in vertex.globals_with(Qualifier::Uniform).chain(fragment.globals_with(Qualifier::Uniform)) {
+            match uniforms.get(&uniform.name) {
+                Some(existing) if *existing != (uniform.ty, uniform.array) => {
+                    return Err(GpuError::ShaderError(format!("uniform {} differs between stages", uniform.name)));
+                }
+                _ => {
+                    uniforms.insert(uniform.name.clone(), (uniform.ty, uniform.array));
+                    values.insert(uniform.name.clone(), uniform.zero());
+                }
+            }
+        }
+
+        let output = if fragment.version >= 300 {
+            let mut outputs: Vec<_> = fragment.globals_with(Qualifier::FragmentOutput).collect();
+            outputs.sort_by_key(|o| o.location.unwrap_or(0));
+            outputs.first().map(|o| o.name.clone()).unwrap_or_default()
+        } else {
+            "gl_FragColor".to_string()
+        };
+
+        let id = self.alloc_id();
+        self.programs.insert(
+            id,
+            Program {
+                vertex: vertex.clone(),
+                fragment: fragment.clone(),
+                attributes,
+                varyings,
+                uniforms,
+                values,
+                output,
+            },
+        );
+        Ok(id)
+    }
+
+    pub fn delete_program(&mut self, id: u32) {
+        self.programs.remove(&id);
+        if self.program == Some(id) {
+            self.program = None;
+        }
+    }
+
+    pub fn use_program(&mut self, id: Option<u32>) {
+        self.program = id;
+    }
+
+    pub fn attrib_location(&self, program: u32, name: &str) -> Option<u32> {
+        let program = self.programs.get(&program)?;
+        program.attributes.iter().find(|(n, _, _)| n == name).map(|(_, _, l)| *l)
+    }
+
+    pub fn active_attributes(&self, program: u32) -> Vec<ActiveInfo> {
+        self.programs
+            .get(&program)
+            .map(|p| p.attributes.iter().map(|(name, ty, _)| ActiveInfo { name: name.clone(), ty: *ty, size: 1 }).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn active_uniforms(&self, program: u32) -> Vec<ActiveInfo> {
+        let Some(program) = self.programs.get(&program) else { return Vec::new() };
+        let mut uniforms: Vec<_> = program
+            .uniforms
+            .iter()
+            .map(|(name, (ty, array))| ActiveInfo {
+                name: if array.is_some() { format!("{}[0]", name) } else { name.clone() },
+                ty: *ty,
+                size: array.unwrap_or(1),
+            })
+            .collect();
+        uniforms.sort_by(|a, b| a.name.cmp(&b.name));
+        uniforms
+    }
+
+    /// Type of a uniform by location name, e.g. `u_lights[2]`
+    pub fn uniform_type(&self, program: u32, name: &str) -> Option<Type> {
+        let (base, index) = split_uniform_name(name)?;
+        let (ty, array) = self.programs.get(&program)?.uniforms.get(base)?;
+        match array {
+            Some(n) if index < *n => Some(*ty),
+            None if index == 0 => Some(*ty),
+            _ => None,
+        }
+    }
+
+    /// Set a uniform from flattened components; arrays fill from the named element
+    pub fn set_uniform(&mut self, program: u32, name: &str, data: &[f32]) -> Result<(), GpuError> {
+        let program = self.programs.get_mut(&program).ok_or_else(|| unknown("program", program))?;
+        let (base, index) = split_uniform_name(name).ok_or_else(|| unknown_uniform(name))?;
+        let (ty, array) = *program.uniforms.get(base).ok_or_else(|| unknown_uniform(name))?;
+        let slot = program.values.get_mut(base).ok_or_else(|| unknown_uniform(name))?;
+        let stride = ty.components().max(1);
+        match (slot, array) {
+            (Value::Array(items), Some(_)) => {
+                for (item, chunk) in items.iter_mut().skip(index).zip(data.chunks(stride)) {
+                    *item = Value::from_components(ty, chunk);
+                }
+            }
+            (slot, None) if index == 0 => *slot = Value::from_components(ty, data),
+            _ => return Err(unknown_uniform(name)),
+        }
+        Ok(())
+    }
+
+    pub fn uniform_value(&self, program: u32, name: &str) -> Option<Value> {
+        let (base, index) = split_uniform_name(name)?;
+        match self.programs.get(&program)?.values.get(base)? {
+            Value::Array(items) => items.get(index).cloned(),
+            value => Some(value.clone()),
+        }
+    }
+
+    // Vertex attributes
+
+    pub fn enable_vertex_attrib(&mut self, index: usize, enabled: bool) {
+        if let Some(slot) = self.attribs.get_mut(index) {
+            slot.enabled = enabled;
+        }
+    }
+
+    pub fn vertex_attrib_pointer(&mut self, index: usize, pointer: AttribPointer) -> Result<(), GpuError> {
+        if !self.buffers.contains_key(&pointer.buffer) {
+            return Err(unknown("buffer", pointer.buffer));
+        }
+        let slot = self.attribs.get_mut(index).ok_or_else(|| unknown("attribute", index as u32))?;
+        slot.pointer = Some(pointer);
+        Ok(())
+    }
+
+    /// Set the value a disabled attribute array reads
+    pub fn vertex_attrib(&mut self, index: usize, value: [f32; 4]) {
+        if let Some(slot) = self.attribs.get_mut(index) {
+            slot.constant = value;
+        }
+    }
+
+    /// Advance the attribute once per `divisor` instances; 0 means per vertex
+    pub fn vertex_attrib_divisor(&mut self, index: usize, divisor: u32) {
+        if let Some(slot) = self.attribs.get_mut(index) {
+            slot.divisor = divisor;
+        }
+    }
+
+    // Drawing
+
+    /// Clear the color and/or depth buffer of the bound framebuffer
+    pub fn clear(&mut self, color: bool, depth: bool) -> Result<(), GpuError> {
+        let mut target = self.take_target()?;
+        let (r, g, b, a) = {
+            let c = self.clear_color.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+            (c[0], c[1], c[2], c[3])
+        };
+        let rect = self.clip_rect(&target, false);
+        for y in rect.1..rect.3 {
+            for x in rect.0..rect.2 {
+                let i = y * target.width + x;
+                if color {
+                    for (c, value) in [r, g, b, a].into_iter().enumerate() {
+                        if self.color_mask[c] {
+                            target.color[i * 4 + c] = value;
+                        }
+                    }
+                }
+                if depth && self.depth_mask && !target.depth.is_empty() {
+                    target.depth[i] = self.clear_depth.clamp(0.0, 1.0);
+                }
+            }
+        }
+        self.restore_target(target);
+        Ok(())
+    }
+
+    pub fn draw_arrays(&mut self, mode: Primitive, first: u32, count: u32, instances: u32) -> Result<(), GpuError> {
+        let indices: Vec<u32> = (first..first.saturating_add(count)).collect();
+        self.draw(mode, &indices, instances)
+    }
+
+    /// Draw with indices read from the bound element buffer at a byte offset
+    pub fn draw_elements(
+        &mut self,
+        mode: Primitive,
+        count: usize,
+        index_type: ComponentType,
+        offset: usize,
+        instances: u32,
+    ) -> Result<(), GpuError> {
+        let buffer = self
+            .element_buffer
+            .and_then(|id| self.buffers.get(&id))
+            .ok_or_else(|| GpuError::DeviceError("no element array buffer bound".into()))?;
+        let size = index_type.size();
+        let end = offset + count * size;
+        if end > buffer.len() || !offset.is_multiple_of(size) {
+            return Err(GpuError::DeviceError("indices read past the end of the element buffer".into()));
+        }
+        let indices: Vec<u32> = buffer[offset..end].chunks(size).map(|c| index_type.read(c, false) as u32).collect();
+        self.draw(mode, &indices, instances)
+    }
+
+    fn draw(&mut self, mode: Primitive, indices: &[u32], instances: u32) -> Result<(), GpuError> {
+        if indices.is_empty() || instances == 0 {
+            return Ok(());
+        }
+        let mut target = self.take_target()?;
+        let result = self.render(mode, indices, instances, &mut target);
+        self.restore_target(target);
+        result
+    }
+
+    /// Read RGBA8 pixels from the bound framebuffer; row 0 is the bottom row
+    pub fn read_pixels(&self, x: i32, y: i32, width: u32, height: u32) -> Vec<u8> {
+        let (w, h) = (width as usize, height as usize);
+        let mut out = vec![0; w * h * 4];
+        let (color, tw, th) = match self.framebuffer.and_then(|id| self.framebuffers.get(&id)) {
+            Some(fb) => match fb.color.and_then(|t| self.textures.get(&t)) {
+                Some(t) => (&t.data, t.width, t.height),
+                None => return out,
+            },
+            None => (&self.color, self.width, self.height),
+        };
+        for row in 0..h {
+            let sy = y + row as i32;
+            if sy < 0 || sy as usize >= th {
+                continue;
+            }
+            for col in 0..w {
+                let sx = x + col as i32;
+                if sx < 0 || sx as usize >= tw {
+                    continue;
+                }
+                let src = (sy as usize * tw + sx as usize) * 4;
+                let dst = (row * w + col) * 4;
+                out[dst..dst + 4].copy_from_slice(&color[src..src + 4]);
+            }
+        }
+        out
+    }
+
+    /// Default framebuffer as top-down RGBA8 for compositing
+    pub fn frame(&self) -> Vec<u8> {
+        let stride = self.width * 4;
+        let mut out = Vec::with_capacity(self.color.len());
+        for row in self.color.chunks(stride.max(1)).rev() {
+            out.extend_from_slice(row);
+        }
+        out
+    }
+
+    fn take_target(&mut self) -> Result<Target, GpuError> {
+        let Some(id) = self.framebuffer else {
+            return Ok(Target {
+                color: std::mem::take(&mut self.color),
+                depth: std::mem::take(&mut self.depth),
+                width: self.width,
+                height: self.height,
+            });
+        };
+        if !self.framebuffer_complete() {
+            return Err(GpuError::DeviceError("framebuffer is incomplete".into()));
+        }
+        let fb = self.framebuffers.get_mut(&id).ok_or_else(|| unknown("framebuffer", id))?;
+        let depth = std::mem::take(&mut fb.depth_data);
+        let texture = fb.color.and_then(|t| self.textures.get_mut(&t)).ok_or_else(|| unknown("texture", 0))?;
+        Ok(Target { color: std::mem::take(&mut texture.data), depth, width: texture.width, height: texture.height })
+    }
+
+    fn restore_target(&mut self, target: Target) {
+        match self.framebuffer.and_then(|id| self.framebuffers.get_mut(&id)) {
+            Some(fb) => {
+                fb.depth_data = target.depth;
+                if let Some(texture) = fb.color.and_then(|t| self.textures.get_mut(&t)) {
+                    texture.data = target.color;
+                }
+            }
+            None => {
+                self.color = target.color;
+                self.depth = target.depth;
+            }
+        }
+    }
+
+    /// Pixel bounds (x0, y0, x1, y1) writable in the target
+    fn clip_rect(&self, target: &Target, viewport: bool) -> (usize, usize, usize, usize) {
+        let mut rect = (0i32, 0i32, target.width as i32, target.height as i32);
+        let mut intersect = |(x, y, w, h): (i32, i32, i32, i32)| {
+            rect = (rect.0.max(x), rect.1.max(y), rect.2.min(x + w), rect.3.min(y + h));
+        };
+        if viewport {
+            intersect(self.viewport);
+        }
+        if let Some(scissor) = self.scissor {
+            intersect(scissor);
+        }
+        let x0 = rect.0.max(0) as usize;
+        let y0 = rect.1.max(0) as usize;
+        (x0, y0, (rect.2.max(0) as usize).max(x0), (rect.3.max(0) as usize).max(y0))
+    }
+
+    fn fetch_attribute(&self, slot: &AttribSlot, vertex: u32, instance: u32) -> Result<[f32; 4], GpuError> {
+        let mut out = [0.0, 0.0, 0.0, 1.0];
+        let pointer = match (&slot.pointer, slot.enabled) {
+            (Some(pointer), true) => pointer,
+            _ => return Ok(slot.constant),
+        };
+        let buffer = self.buffers.get(&pointer.buffer).ok_or_else(|| unknown("buffer", pointer.buffer))?;
+        let element = instance.checked_div(slot.divisor).unwrap_or(vertex) as usize;
+        let size = pointer.component.size();
+        let stride = if pointer.stride == 0 { pointer.size * size } else { pointer.stride };
+        let start = pointer.offset + element * stride;
+        let end = start + pointer.size * size;
+        if end > buffer.len() {
+            return Err(GpuError::DeviceError("vertex attribute reads past the end of its buffer".into()));
+        }
+        for (c, bytes) in buffer[start..end].chunks(size).enumerate().take(4) {
+            out[c] = pointer.component.read(bytes, pointer.normalized);
+        }
+        Ok(out)
+    }
+
+    fn render(&self, mode: Primitive, indices: &[u32], instances: u32, target: &mut Target) -> Result<(), GpuError> {
+        let program = self
+            .program
+            .and_then(|id| self.programs.get(&id))
+            .ok_or_else(|| GpuError::DeviceError("no program in use".into()))?;
+        let sampler = Sampler { textures: &self.textures, units: &self.texture_units };
+
+        let mut vertex_exec = Executor::new(&program.vertex, &sampler);
+        let mut fragment_exec = Executor::new(&program.fragment, &sampler);
+        for (name, value) in &program.values {
+            vertex_exec.set(name, value.clone());
+            fragment_exec.set(name, value.clone());
+        }
+
+        let mut raster = Raster {
+            device: self,
+            program,
+            fragment: fragment_exec,
+            rect: self.clip_rect(target, true),
+            target,
+        };
+
+        for instance in 0..instances {
+            // Shade each referenced vertex once
+            let mut shaded: HashMap<u32, ClipVertex> = HashMap::new();
+            let mut vertices = Vec::with_capacity(indices.len());
+            for &index in indices {
+                if let Some(v) = shaded.get(&index) {
+                    vertices.push(v.clone());
+                    continue;
+                }
+                for (name, ty, location) in &program.attributes {
+                    let value = self.fetch_attribute(&self.attribs[*location as usize], index, instance)?;
+                    vertex_exec.set(name, Value::from_components(*ty, &value));
+                }
+                vertex_exec.set("gl_VertexID", Value::Int(index as i32));
+                vertex_exec.set("gl_InstanceID", Value::Int(instance as i32));
+                vertex_exec.run()?;
+                let mut varyings = Vec::new();
+                for (name, _) in &program.varyings {
+                    if let Some(value) = vertex_exec.get(name) {
+                        varyings.extend(value.to_components());
+                    }
+                }
+                let vertex = ClipVertex {
+                    position: vertex_exec.get("gl_Position").map(Value::as_vec4).unwrap_or([0.0; 4]),
+                    point_size: vertex_exec.get("gl_PointSize").map(Value::as_f32).unwrap_or(1.0),
+                    varyings,
+                };
+                shaded.insert(index, vertex.clone());
+                vertices.push(vertex);
+            }
+
+            match mode {
+                Primitive::Points => {
+                    for v in &vertices {
+                        raster.point(v)?;
+                    }
+                }
+                Primitive::Lines => {
+                    for pair in vertices.chunks_exact(2) {
+                        raster.line(&pair[0], &pair[1])?;
+                    }
+                }
+                Primitive::LineStrip | Primitive::LineLoop => {
+                    for pair in vertices.windows(2) {
+                        raster.line(&pair[0], &pair[1])?;
+                    }
+                    if mode == Primitive::LineLoop && vertices.len() > 2 {
+                        raster.line(&vertices[vertices.len() - 1], &vertices[0])?;
+                    }
+                }
+                Primitive::Triangles => {
+                    for tri in vertices.chunks_exact(3) {
+                        raster.triangle(&tri[0], &tri[1], &tri[2])?;
+                    }
+                }
+                Primitive::TriangleStrip => {
+                    for i in 2..vertices.len() {
+                        // Alternate order to keep winding consistent
+                        let (a, b) = if i % 2 == 0 { (i - 2, i - 1) } else { (i - 1, i - 2) };
+                        raster.triangle(&vertices[a], &vertices[b], &vertices[i])?;
+                    }
+                }
+                Primitive::TriangleFan => {
+                    for i in 2..vertices.len() {
+                        raster.triangle(&vertices[0], &vertices[i - 1], &vertices[i])?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unknown(kind: &str, id: u32) -> GpuError {
+    GpuError::DeviceError(format!("unknown {} {}", kind, id))
+}
+
+fn unknown_uniform(name: &str) -> GpuError {
+    GpuError::DeviceError(format!("unknown uniform {}", name))
+}
+
+/// Split `name[3]` into (`name`, 3)
+fn split_uniform_name(name: &str) -> Option<(&str, usize)> {
+    match name.strip_suffix(']').and_then(|n| n.split_once('[')) {
+        Some((base, index)) => Some((base, index.parse().ok()?)),
+        None => Some((name, 0)),
+    }
+}
+
+fn write_pixels(
+    texture: &mut Texture,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    data: &[u8],
+) -> Result<(), GpuError> {
+    let channels = format.channels();
+    if data.len() < width * height * channels {
+        return Err(GpuError::DeviceError("not enough pixel data for the upload".into()));
+    }
+    for row in 0..height {
+        for col in 0..width {
+            let src = (row * width + col) * channels;
+            let dst = ((y + row) * texture.width + x + col) * 4;
+            texture.data[dst..dst + 4].copy_from_slice(&format.to_rgba(&data[src..src + channels]));
+        }
+    }
+    Ok(())
+}
+
+struct Target {
+    color: Vec<u8>,
+    depth: Vec<f32>,
+    width: usize,
+    height: usize,
+}
+
+struct Sampler<'a> {
+    textures: &'a HashMap<u32, Texture>,
+    units: &'a [Option<u32>; MAX_TEXTURE_UNITS],
+}
+
+impl TextureSampler for Sampler<'_> {
+    fn sample(&self, unit: i32, s: f32, t: f32) -> [f32; 4] {
+        let texture = usize::try_from(unit)
+            .ok()
+            .and_then(|u| self.units.get(u).copied().flatten())
+            .and_then(|id| self.textures.get(&id));
+        match texture {
+            Some(texture) if texture.is_complete() => texture.sample(s, t),
+            // Incomplete textures sample as opaque black
+            _ => [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ClipVertex {
+    position: [f32; 4],
+    point_size: f32,
+    varyings: Vec<f32>,
+}
+
+impl ClipVertex {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            position: std::array::from_fn(|i| self.position[i] + (other.position[i] - self.position[i]) * t),
+            point_size: self.point_size + (other.point_size - self.point_size) * t,
+            varyings: self.varyings.iter().zip(&other.varyings).map(|(a, b)| a + (b - a) * t).collect(),
+        }
+    }
+}
+
+/// Vertex after perspective divide and viewport transform
+struct ScreenVertex<'v> {
+    x: f32,
+    y: f32,
+    z: f32,
+    inv_w: f32,
+    varyings: &'v [f32],
+}
+
+/// Clip distances for the near plane and the w > 0 guard
+fn clip_distances(p: [f32; 4]) -> [f32; 2] {
+    [p[2] + p[3], p[3] - 1e-6]
+}
+
+struct Raster<'a, 't> {
+    device: &'a GlDevice,
+    program: &'a Program,
+    fragment: Executor<'a>,
+    rect: (usize, usize, usize, usize),
+    target: &'t mut Target,
+}
+
+impl Raster<'_, '_> {
+    fn to_screen<'v>(&self, v: &'v ClipVertex) -> ScreenVertex<'v> {
+        let (vx, vy, vw, vh) = self.device.viewport;
+        let inv_w = 1.0 / v.position[3];
+        let ndc = [v.position[0] * inv_w, v.position[1] * inv_w, v.position[2] * inv_w];
+        ScreenVertex {
+            x: vx as f32 + (ndc[0] + 1.0) * 0.5 * vw as f32,
+            y: vy as f32 + (ndc[1] + 1.0) * 0.5 * vh as f32,
+            z: (ndc[2] + 1.0) * 0.5,
+            inv_w,
+            varyings: &v.varyings,
+        }
+    }
+
+    fn triangle(&mut self, a: &ClipVertex, b: &ClipVertex, c: &ClipVertex) -> Result<(), GpuError> {
+        // Sutherland-Hodgman against each clip plane
+        let mut polygon = vec![a.clone(), b.clone(), c.clone()];
+        for plane in 0..2 {
+            let mut clipped = Vec::with_capacity(polygon.len() + 1);
+            for i in 0..polygon.len() {
+                let (p, q) = (&polygon[i], &polygon[(i + 1) % polygon.len()]);
+                let (dp, dq) = (clip_distances(p.position)[plane], clip_distances(q.position)[plane]);
+                if dp >= 0.0 {
+                    clipped.push(p.clone());
+                }
+                if (dp >= 0.0) != (dq >= 0.0) {
+                    clipped.push(p.lerp(q, dp / (dp - dq)));
+                }
+            }
+            polygon = clipped;
+            if polygon.len() < 3 {
+                return Ok(());
+            }
+        }
+        for i in 2..polygon.len() {
+            self.raster_triangle(&polygon[0], &polygon[i - 1], &polygon[i])?;
+        }
+        Ok(())
+    }
+
+    fn raster_triangle(&mut self, a: &ClipVertex, b: &ClipVertex, c: &ClipVertex) -> Result<(), GpuError> {
+        let (a, mut b, mut c) = (self.to_screen(a), self.to_screen(b), self.to_screen(c));
+        let area = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+        if area == 0.0 || !area.is_finite() {
+            return Ok(());
+        }
+        let front = (area > 0.0) == self.device.front_ccw;
+        match self.device.cull_face {
+            Some(CullFace::FrontAndBack) => return Ok(()),
+            Some(CullFace::Front) if front => return Ok(()),
+            Some(CullFace::Back) if !front => return Ok(()),
+            _ => {}
+        }
+        if area < 0.0 {
+            std::mem::swap(&mut b, &mut c);
+        }
+        let area = area.abs();
+        let verts = [&a, &b, &c];
+
+        let (x0, y0, x1, y1) = self.rect;
+        let min_x = (verts.iter().map(|v| v.x).fold(f32::MAX, f32::min).floor().max(x0 as f32)) as usize;
+        let max_x = (verts.iter().map(|v| v.x).fold(f32::MIN, f32::max).ceil().min(x1 as f32)) as usize;
+        let min_y = (verts.iter().map(|v| v.y).fold(f32::MAX, f32::min).floor().max(y0 as f32)) as usize;
+        let max_y = (verts.iter().map(|v| v.y).fold(f32::MIN, f32::max).ceil().min(y1 as f32)) as usize;
+
+        // Edge i is opposite vertex i, walked counter-clockwise
+        let edges = [(&b, &c), (&c, &a), (&a, &b)];
+        let mut varyings = vec![0.0; a.varyings.len()];
+        for py in min_y..max_y {
+            for px in min_x..max_x {
+                let (x, y) = (px as f32 + 0.5, py as f32 + 0.5);
+                let mut weights = [0.0; 3];
+                let mut inside = true;
+                for (i, (p, q)) in edges.iter().enumerate() {
+                    let (dx, dy) = (q.x - p.x, q.y - p.y);
+                    let e = dx * (y - p.y) - dy * (x - p.x);
+                    // Top-left rule: shared edges belong to exactly one triangle
+                    let owns_edge = dy < 0.0 || (dy == 0.0 && dx < 0.0);
+                    if e < 0.0 || (e == 0.0 && !owns_edge) {
+                        inside = false;
+                        break;
+                    }
+                    weights[i] = e / area;
+                }
+                if !inside {
+                    continue;
+                }
+                let z = weights.iter().zip(&verts).map(|(w, v)| w * v.z).sum::<f32>();
+                let inv_w = weights.iter().zip(&verts).map(|(w, v)| w * v.inv_w).sum::<f32>();
+                for (k, out) in varyings.iter_mut().enumerate() {
+                    *out = weights.iter().zip(&verts).map(|(w, v)| w * v.inv_w * v.varyings[k]).sum::<f32>() / inv_w;
+                }
+                self.fragment(px, py, z, inv_w, front, &varyings, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, a: &ClipVertex, b: &ClipVertex) -> Result<(), GpuError> {
+        let (mut a, mut b) = (a.clone(), b.clone());
+        for plane in 0..2 {
+            let (da, db) = (clip_distances(a.position)[plane], clip_distances(b.position)[plane]);
+            match (da >= 0.0, db >= 0.0) {
+                (false, false) => return Ok(()),
+                (false, true) => a = a.lerp(&b, da / (da - db)),
+                (true, false) => b = a.lerp(&b, da / (da - db)),
+                (true, true) => {}
+            }
+        }
+        let (sa, sb) = (self.to_screen(&a), self.to_screen(&b));
+        let steps = (sb.x - sa.x).abs().max((sb.y - sa.y).abs()).ceil().max(1.0) as usize;
+        let mut varyings = vec![0.0; a.varyings.len()];
+        // Half-open: the last pixel belongs to the next segment
+        for step in 0..steps {
+            let t = (step as f32 + 0.5) / steps as f32;
+            let (x, y) = (sa.x + (sb.x - sa.x) * t, sa.y + (sb.y - sa.y) * t);
+            if x < 0.0 || y < 0.0 {
+                continue;
+            }
+            let inv_w = sa.inv_w + (sb.inv_w - sa.inv_w) * t;
+            for (k, out) in varyings.iter_mut().enumerate() {
+                let va = sa.varyings[k] * sa.inv_w;
+                let vb = sb.varyings[k] * sb.inv_w;
+                *out = (va + (vb - va) * t) / inv_w;
+            }
+            let z = sa.z + (sb.z - sa.z) * t;
+            self.fragment(x as usize, y as usize, z, inv_w, true, &varyings, None)?;
+        }
+        Ok(())
+    }
+
+    fn point(&mut self, v: &ClipVertex) -> Result<(), GpuError> {
+        if clip_distances(v.position).iter().any(|d| *d < 0.0) {
+            return Ok(());
+        }
+        let s = self.to_screen(v);
+        let size = v.point_size.clamp(1.0, 64.0);
+        let half = size / 2.0;
+        let x_start = (s.x - half).round().max(0.0) as usize;
+        let y_start = (s.y - half).round().max(0.0) as usize;
+        let x_end = (s.x + half).round().max(0.0) as usize;
+        let y_end = (s.y + half).round().max(0.0) as usize;
+        let varyings = v.varyings.clone();
+        for py in y_start..y_end {
+            for px in x_start..x_end {
+                let coord_s = (px as f32 + 0.5 - (s.x - half)) / size;
+                // gl_PointCoord runs top to bottom
+                let coord_t = 1.0 - (py as f32 + 0.5 - (s.y - half)) / size;
+                self.fragment(px, py, s.z, s.inv_w, true, &varyings, Some([coord_s, coord_t]))?;
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn fragment(
+        &mut self,
+        px: usize,
+        py: usize,
+        z: f32,
+        inv_w: f32,
+        front: bool,
+        varyings: &[f32],
+        point_coord: Option<[f32; 2]>,
+    ) -> Result<(), GpuError> {
+        let (x0, y0, x1, y1) = self.rect;
+        if px < x0 || px >= x1 || py < y0 || py >= y1 || !(0.0..=1.0).contains(&z) {
+            return Ok(());
+        }
+        let index = py * self.target.width + px;
+        let has_depth = !self.target.depth.is_empty();
+        let depth_fails = |func: CompareFunc| !func.test(z, self.target.depth[index]);
+        if has_depth && self.device.depth_func.is_some_and(depth_fails) {
+            return Ok(());
+        }
+
+        let mut offset = 0;
+        for (name, ty) in &self.program.varyings {
+            let n = ty.components();
+            self.fragment.set(name, Value::from_components(*ty, &varyings[offset..offset + n]));
+            offset += n;
+        }
+        self.fragment.set("gl_FragCoord", Value::Vec(4, [px as f32 + 0.5, py as f32 + 0.5, z, inv_w]));
+        self.fragment.set("gl_FrontFacing", Value::Bool(front));
+        if let Some([s, t]) = point_coord {
+            self.fragment.set("gl_PointCoord", Value::Vec(2, [s, t, 0.0, 0.0]));
+        }
+        if !self.fragment.run()? {
+            return Ok(());
+        }
+
+        let color = self.fragment.get(&self.program.output).map(Value::as_vec4).unwrap_or([0.0; 4]);
+        let src = color.map(|c| c.clamp(0.0, 1.0));
+        let pixel = &mut self.target.color[index * 4..index * 4 + 4];
+        let out = match &self.device.blend {
+            Some(blend) => blend.blend(src, std::array::from_fn(|c| pixel[c] as f32 / 255.0)),
+            None => src,
+        };
+        for (c, value) in out.iter().enumerate() {
+            if self.device.color_mask[c] {
+                pixel[c] = (value * 255.0).round() as u8;
+            }
+        }
+        if self.device.depth_func.is_some() && self.device.depth_mask && has_depth {
+            self.target.depth[index] = z;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu::glsl::translate;
+
+    const VERTEX: &str = r#"
+        attribute vec2 a_position;
+        attribute vec3 a_color;
+        varying vec3 v_color;
+        void main() {
+            v_color = a_color;
+            gl_Position = vec4(a_position, 0.0, 1.0);
+        }
+    "#;
+
+    const FRAGMENT: &str = r#"
+        precision mediump float;
+        uniform float u_alpha;
+        varying vec3 v_color;
+        void main() { gl_FragColor = vec4(v_color, u_alpha); }
+    "#;
+
+    fn floats(data: &[f32]) -> Vec<u8> {
+        data.iter().flat_map(|f| f.to_le_bytes()).collect()
+    }
+
+    fn program(device: &mut GlDevice) -> u32 {
+        let vs = translate(VERTEX, Stage::Vertex).unwrap();
+        let fs = translate(FRAGMENT, Stage::Fragment).unwrap();
+        let program = device.link_program(&vs, &fs, &[("a_color".into(), 0)]).unwrap();
+        device.use_program(Some(program));
+        device.set_uniform(program, "u_alpha", &[1.0]).unwrap();
+        program
+    }
+
+    #[test]
+    fn test_draw_quad_and_read_pixels() {
+        let mut device = GlDevice::new(8, 8);
+        let program = program(&mut device);
+        assert_eq!(device.attrib_location(program, "a_color"), Some(0));
+        assert_eq!(device.attrib_location(program, "a_position"), Some(1));
+
+        // Left half red, interleaved position and color
+        let buffer = device.create_buffer();
+        #[rustfmt::skip]
+        let vertices = floats(&[
+            -1.0, -1.0, 1.0, 0.0, 0.0,
+             0.0, -1.0, 1.0, 0.0, 0.0,
+            -1.0,  1.0, 1.0, 0.0, 0.0,
+             0.0,  1.0, 1.0, 0.0, 0.0,
+        ]);
+        device.buffer_data(buffer, &vertices).unwrap();
+        let pointer = |size, offset| AttribPointer {
+            buffer,
+            size,
+            component: ComponentType::Float,
+            normalized: false,
+            stride: 20,
+            offset,
+        };
+        device.vertex_attrib_pointer(1, pointer(2, 0)).unwrap();
+        device.vertex_attrib_pointer(0, pointer(3, 8)).unwrap();
+        device.enable_vertex_attrib(0, true);
+        device.enable_vertex_attrib(1, true);
+
+        device.clear_color = [0.0, 0.0, 1.0, 1.0];
+        device.clear(true, true).unwrap();
+        device.draw_arrays(Primitive::TriangleStrip, 0, 4, 1).unwrap();
+
+        let pixels = device.read_pixels(0, 0, 8, 8);
+        let at = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..(y * 8 + x) * 4 + 4];
+        assert_eq!(at(0, 0), [255, 0, 0, 255]);
+        assert_eq!(at(3, 7), [255, 0, 0, 255]);
+        assert_eq!(at(4, 0), [0, 0, 255, 255]);
+
+        // Blending the same quad at half alpha over itself keeps every pixel covered once
+        device.set_uniform(program, "u_alpha", &[0.5]).unwrap();
+        device.vertex_attrib(0, [0.0, 1.0, 0.0, 1.0]);
+        device.enable_vertex_attrib(0, false);
+        device.blend = Some(BlendState {
+            src_rgb: BlendFactor::SrcAlpha,
+            dst_rgb: BlendFactor::OneMinusSrcAlpha,
+            ..BlendState::default()
+        });
+        device.draw_arrays(Primitive::TriangleStrip, 0, 4, 1).unwrap();
+        let pixels = device.read_pixels(0, 0, 8, 8);
+        for y in 0..8 {
+            for x in 0..4 {
+                let i = (y * 8 + x) * 4;
+                assert_eq!(pixels[i..i + 3], [128, 128, 0], "pixel {} {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_to_texture_with_depth() {
+        let mut device = GlDevice::new(4, 4);
+        program(&mut device);
+
+        let texture = device.create_texture();
+        device.tex_image_2d(texture, 4, 4, PixelFormat::Rgba, None).unwrap();
+        let depth = device.create_renderbuffer();
+        device.renderbuffer_storage(depth, 4, 4).unwrap();
+        let fb = device.create_framebuffer();
+        device.framebuffer_texture(fb, Some(texture)).unwrap();
+        assert!(device.framebuffer_depth(fb, Some(depth)).is_ok());
+        device.bind_framebuffer(Some(fb));
+        assert!(device.framebuffer_complete());
+
+        device.depth_func = Some(CompareFunc::Less);
+        device.clear(true, true).unwrap();
+
+        // Indexed triangles: near green, then far red which must be hidden
+        let buffer = device.create_buffer();
+        device.buffer_data(buffer, &floats(&[-1.0, -1.0, 3.0, -1.0, -1.0, 3.0])).unwrap();
+        let indices = device.create_buffer();
+        device.buffer_data(indices, &[0, 0, 1, 0, 2, 0]).unwrap();
+        device.bind_element_buffer(Some(indices));
+        device.vertex_attrib_pointer(
+            1,
+            AttribPointer { buffer, size: 2, component: ComponentType::Float, normalized: false, stride: 0, offset: 0 },
+        )
+        .unwrap();
+        device.enable_vertex_attrib(1, true);
+
+        device.vertex_attrib(0, [0.0, 1.0, 0.0, 1.0]);
+        device.draw_elements(Primitive::Triangles, 3, ComponentType::UnsignedShort, 0, 1).unwrap();
+        device.vertex_attrib(0, [1.0, 0.0, 0.0, 1.0]);
+        device.draw_elements(Primitive::Triangles, 3, ComponentType::UnsignedShort, 0, 1).unwrap();
+        assert_eq!(device.read_pixels(2, 2, 1, 1), [0, 255, 0, 255]);
+
+        // Default framebuffer is untouched
+        device.bind_framebuffer(None);
+        assert_eq!(device.read_pixels(2, 2, 1, 1), [0, 0, 0, 0]);
+        assert_eq!(device.texture_size(texture), Some((4, 4)));
+    }
+}
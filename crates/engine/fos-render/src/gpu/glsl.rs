@@ -0,0 +1,1845 @@
+//! GLSL ES Translation
+//!
+//! Translates GLSL ES 1.00 and 3.00 shaders into a checked syntax tree and
+//! executes them per vertex and per fragment for the GL device. Covers what
+//! WebGL content uses in practice: scalar, vector and matrix math, swizzles,
+//! control flow, user functions with out parameters, uniform arrays and 2D
+//! texture sampling. Integer and boolean vectors are carried as float vectors.
+
+use std::collections::HashMap;
+
+use super::GpuError;
+
+/// Shader stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Vertex,
+    Fragment,
+}
+
+/// GLSL type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Void,
+    Bool,
+    Int,
+    Float,
+    /// vecN, ivecN and bvecN
+    Vec(u8),
+    /// Square matN
+    Mat(u8),
+    Sampler2D,
+    SamplerCube,
+}
+
+impl Type {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "void" => Self::Void,
+            "bool" => Self::Bool,
+            "int" | "uint" => Self::Int,
+            "float" => Self::Float,
+            "vec2" | "ivec2" | "bvec2" | "uvec2" => Self::Vec(2),
+            "vec3" | "ivec3" | "bvec3" | "uvec3" => Self::Vec(3),
+            "vec4" | "ivec4" | "bvec4" | "uvec4" => Self::Vec(4),
+            "mat2" => Self::Mat(2),
+            "mat3" => Self::Mat(3),
+            "mat4" => Self::Mat(4),
+            "sampler2D" => Self::Sampler2D,
+            "samplerCube" => Self::SamplerCube,
+            _ => return None,
+        })
+    }
+
+    /// Zero value of the type
+    pub fn zero(&self) -> Value {
+        match self {
+            Self::Void => Value::Void,
+            Self::Bool => Value::Bool(false),
+            Self::Int => Value::Int(0),
+            Self::Float => Value::Float(0.0),
+            Self::Vec(n) => Value::Vec(*n, [0.0; 4]),
+            Self::Mat(n) => Value::Mat(*n, [0.0; 16]),
+            Self::Sampler2D | Self::SamplerCube => Value::Sampler(0),
+        }
+    }
+
+    /// Number of float components
+    pub fn components(&self) -> usize {
+        match self {
+            Self::Void => 0,
+            Self::Vec(n) => *n as usize,
+            Self::Mat(n) => (*n as usize) * (*n as usize),
+            _ => 1,
+        }
+    }
+}
+
+/// Runtime value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Void,
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Vec(u8, [f32; 4]),
+    /// Column-major, columns at a stride of 4
+    Mat(u8, [f32; 16]),
+    /// Texture unit
+    Sampler(i32),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_f32(&self) -> f32 {
+        match self {
+            Self::Bool(b) => *b as i32 as f32,
+            Self::Int(i) => *i as f32,
+            Self::Float(f) => *f,
+            Self::Vec(_, v) => v[0],
+            Self::Mat(_, m) => m[0],
+            Self::Sampler(unit) => *unit as f32,
+            _ => 0.0,
+        }
+    }
+
+    pub fn as_bool(&self) -> bool {
+        match self {
+            Self::Bool(b) => *b,
+            other => other.as_f32() != 0.0,
+        }
+    }
+
+    pub fn as_i32(&self) -> i32 {
+        match self {
+            Self::Int(i) => *i,
+            Self::Sampler(unit) => *unit,
+            other => other.as_f32() as i32,
+        }
+    }
+
+    /// Components as a vec4, zero-padded
+    pub fn as_vec4(&self) -> [f32; 4] {
+        match self {
+            Self::Vec(_, v) => *v,
+            Self::Float(_) | Self::Int(_) | Self::Bool(_) => [self.as_f32(), 0.0, 0.0, 0.0],
+            _ => [0.0; 4],
+        }
+    }
+
+    /// Flattened float components, matrices column by column
+    pub fn to_components(&self) -> Vec<f32> {
+        let mut out = Vec::with_capacity(4);
+        self.flatten(&mut out);
+        out
+    }
+
+    fn flatten(&self, out: &mut Vec<f32>) {
+        match self {
+            Self::Vec(n, v) => out.extend_from_slice(&v[..*n as usize]),
+            Self::Mat(n, m) => {
+                for c in 0..*n as usize {
+                    out.extend_from_slice(&m[c * 4..c * 4 + *n as usize]);
+                }
+            }
+            Self::Array(items) => items.iter().for_each(|v| v.flatten(out)),
+            Self::Void => {}
+            other => out.push(other.as_f32()),
+        }
+    }
+
+    /// Build a value of `ty` from flattened components
+    pub fn from_components(ty: Type, data: &[f32]) -> Value {
+        let at = |i: usize| data.get(i).copied().unwrap_or(0.0);
+        match ty {
+            Type::Bool => Value::Bool(at(0) != 0.0),
+            Type::Int => Value::Int(at(0) as i32),
+            Type::Float => Value::Float(at(0)),
+            Type::Vec(n) => {
+                let mut v = [0.0; 4];
+                for (i, c) in v.iter_mut().enumerate().take(n as usize) {
+                    *c = at(i);
+                }
+                Value::Vec(n, v)
+            }
+            Type::Mat(n) => {
+                let mut m = [0.0; 16];
+                for c in 0..n as usize {
+                    for r in 0..n as usize {
+                        m[c * 4 + r] = at(c * n as usize + r);
+                    }
+                }
+                Value::Mat(n, m)
+            }
+            Type::Sampler2D | Type::SamplerCube => Value::Sampler(at(0) as i32),
+            Type::Void => Value::Void,
+        }
+    }
+}
+
+/// Storage qualifier of a global
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Qualifier {
+    None,
+    Const,
+    Uniform,
+    /// `attribute`, or `in` in a 3.00 vertex shader
+    VertexInput,
+    /// `varying`, `in` in a 3.00 fragment shader, or `out` in a 3.00 vertex shader
+    Varying,
+    /// `out` in a 3.00 fragment shader
+    FragmentOutput,
+}
+
+/// Global variable declaration
+#[derive(Debug, Clone)]
+pub struct Global {
+    pub name: String,
+    pub ty: Type,
+    pub array: Option<usize>,
+    pub qualifier: Qualifier,
+    /// `layout(location = N)`
+    pub location: Option<u32>,
+    init: Option<Expr>,
+}
+
+impl Global {
+    /// Zero value, or an array of zero values
+    pub fn zero(&self) -> Value {
+        match self.array {
+            Some(n) => Value::Array(vec![self.ty.zero(); n]),
+            None => self.ty.zero(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParamMode {
+    In,
+    Out,
+    InOut,
+}
+
+#[derive(Debug, Clone)]
+struct Function {
+    name: String,
+    params: Vec<(String, Type, Option<usize>, ParamMode)>,
+    body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+    Xor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnOp {
+    Neg,
+    Not,
+    PreInc,
+    PreDec,
+    PostInc,
+    PostDec,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Lit(Value),
+    Var(String),
+    Unary(UnOp, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Assign(Option<BinOp>, Box<Expr>, Box<Expr>),
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+struct Decl {
+    ty: Type,
+    name: String,
+    array: Option<usize>,
+    init: Option<Expr>,
+}
+
+#[derive(Debug, Clone)]
+enum Stmt {
+    Decls(Vec<Decl>),
+    Expr(Expr),
+    If(Expr, Box<Stmt>, Option<Box<Stmt>>),
+    For(Option<Box<Stmt>>, Option<Expr>, Option<Expr>, Box<Stmt>),
+    While(Expr, Box<Stmt>),
+    DoWhile(Box<Stmt>, Expr),
+    Return(Option<Expr>),
+    Discard,
+    Break,
+    Continue,
+    Block(Vec<Stmt>),
+}
+
+/// A translated shader
+#[derive(Debug, Clone)]
+pub struct ShaderModule {
+    pub stage: Stage,
+    /// 100 or 300
+    pub version: u32,
+    globals: Vec<Global>,
+    functions: Vec<Function>,
+}
+
+/// Translate GLSL ES source for a stage
+pub fn translate(source: &str, stage: Stage) -> Result<ShaderModule, GpuError> {
+    let (text, version) = preprocess(source)?;
+    let tokens = lex(&text)?;
+    let mut parser = Parser { tokens, pos: 0, stage, globals: Vec::new(), functions: Vec::new() };
+    parser.parse_translation_unit()?;
+    if !parser.functions.iter().any(|f| f.name == "main" && f.params.is_empty()) {
+        return Err(err("missing main()"));
+    }
+    Ok(ShaderModule { stage, version, globals: parser.globals, functions: parser.functions })
+}
+
+impl ShaderModule {
+    /// Global declarations in source order
+    pub fn globals(&self) -> &[Global] {
+        &self.globals
+    }
+
+    /// Globals with the given qualifier
+    pub fn globals_with(&self, qualifier: Qualifier) -> impl Iterator<Item = &Global> {
+        self.globals.iter().filter(move |g| g.qualifier == qualifier)
+    }
+}
+
+fn err(message: impl Into<String>) -> GpuError {
+    GpuError::ShaderError(message.into())
+}
+
+// ---------------------------------------------------------------------------
+// Preprocessor and lexer
+// ---------------------------------------------------------------------------
+
+/// Handle directives; returns the remaining source and the language version
+fn preprocess(source: &str) -> Result<(String, u32), GpuError> {
+    let mut defines: HashMap<String, String> = HashMap::new();
+    defines.insert("GL_ES".into(), "1".into());
+    defines.insert("GL_FRAGMENT_PRECISION_HIGH".into(), "1".into());
+    let mut version = 100;
+    // Whether each open conditional is active, and whether a branch was taken
+    let mut stack: Vec<(bool, bool)> = Vec::new();
+    let mut out = String::new();
+
+    // Strip block comments first so directives inside them are ignored
+    let mut text = String::new();
+    let mut rest = source;
+    while let Some(start) = rest.find("/*") {
+        text.push_str(&rest[..start]);
+        match rest[start + 2..].find("*/") {
+            Some(end) => {
+                // Keep line structure for directive parsing
+                text.extend(rest[start..start + 2 + end].chars().filter(|c| *c == '\n'));
+                rest = &rest[start + 2 + end + 2..];
+            }
+            None => return Err(err("unterminated comment")),
+        }
+    }
+    text.push_str(rest);
+
+    for line in text.lines() {
+        let line = match line.find("//") {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let trimmed = line.trim();
+        let active = stack.iter().all(|(on, _)| *on);
+        if let Some(directive) = trimmed.strip_prefix('#') {
+            let directive = directive.trim();
+            let (name, args) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+            let args = args.trim();
+            match name {
+                "version" if active => {
+                    version = if args.starts_with("300") { 300 } else { 100 };
+                }
+                "define" if active => {
+                    let (macro_name, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                    if macro_name.contains('(') {
+                        return Err(err("function-like macros are not supported"));
+                    }
+                    defines.insert(macro_name.to_string(), value.trim().to_string());
+                }
+                "undef" if active => {
+                    defines.remove(args);
+                }
+                "ifdef" | "ifndef" => {
+                    let on = defines.contains_key(args) == (name == "ifdef");
+                    stack.push((on, on));
+                }
+                "if" => {
+                    let on = eval_condition(args, &defines);
+                    stack.push((on, on));
+                }
+                "elif" => {
+                    let (on, taken) = stack.last_mut().ok_or_else(|| err("#elif without #if"))?;
+                    *on = !*taken && eval_condition(args, &defines);
+                    *taken |= *on;
+                }
+                "else" => {
+                    let (on, taken) = stack.last_mut().ok_or_else(|| err("#else without #if"))?;
+                    *on = !*taken;
+                    *taken = true;
+                }
+                "endif" => {
+                    stack.pop().ok_or_else(|| err("#endif without #if"))?;
+                }
+                "error" if active => return Err(err(format!("#error {}", args))),
+                _ => {}
+            }
+            out.push('\n');
+            continue;
+        }
+        if active {
+            out.push_str(&expand(line, &defines));
+        }
+        out.push('\n');
+    }
+    if !stack.is_empty() {
+        return Err(err("unterminated #if"));
+    }
+    Ok((out, version))
+}
+
+/// Evaluate `#if` conditions made of `defined`, integers, `!`, `&&` and `||`
+fn eval_condition(expr: &str, defines: &HashMap<String, String>) -> bool {
+    expr.split("||").any(|clause| {
+        clause.split("&&").all(|term| {
+            let mut term = term.trim();
+            let mut negate = false;
+            while let Some(rest) = term.strip_prefix('!') {
+                negate = !negate;
+                term = rest.trim();
+            }
+            let value = if let Some(rest) = term.strip_prefix("defined") {
+                let name = rest.trim().trim_start_matches('(').trim_end_matches(')').trim();
+                defines.contains_key(name)
+            } else {
+                let term = defines.get(term).map(String::as_str).unwrap_or(term);
+                term.parse::<i64>().map(|v| v != 0).unwrap_or(false)
+            };
+            value != negate
+        })
+    })
+}
+
+/// Substitute object-like macros in a line
+fn expand(line: &str, defines: &HashMap<String, String>) -> String {
+    let mut line = line.to_string();
+    for _ in 0..8 {
+        let mut out = String::with_capacity(line.len());
+        let mut changed = false;
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            if c.is_ascii_alphabetic() || c == '_' {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        end = i + c.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let word = &line[start..end];
+                match defines.get(word) {
+                    Some(value) => {
+                        out.push_str(value);
+                        changed = true;
+                    }
+                    None => out.push_str(word),
+                }
+            } else if c.is_ascii_digit() {
+                // Don't expand suffixes like the `e5` in `1e5`
+                out.push(c);
+                while let Some(&(_, c)) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                        out.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        line = out;
+        if !changed {
+            break;
+        }
+    }
+    line
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Float(f32),
+    Int(i32),
+    Punct(&'static str),
+}
+
+const PUNCTUATION: &[&str] = &[
+    "<<=", ">>=", "++", "--", "+=", "-=", "*=", "/=", "%=", "==", "!=", "<=", ">=", "&&", "||", "^^", "<<", ">>",
+    "+", "-", "*", "/", "%", "<", ">", "=", "!", "(", ")", "[", "]", "{", "}", ".", ",", ";", "?", ":", "&",
+    "|", "^", "~",
+];
+
+fn lex(text: &str) -> Result<Vec<Tok>, GpuError> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i] as char;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            // `true` and `false` stay identifiers; the parser makes them literals
+            tokens.push(Tok::Ident(text[start..i].to_string()));
+        } else if c.is_ascii_digit() || (c == '.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)) {
+            let start = i;
+            if c == '0' && matches!(bytes.get(i + 1), Some(b'x' | b'X')) {
+                i += 2;
+                while i < bytes.len() && bytes[i].is_ascii_hexdigit() {
+                    i += 1;
+                }
+                let value = i64::from_str_radix(&text[start + 2..i], 16).map_err(|_| err("bad hex literal"))?;
+                tokens.push(Tok::Int(value as i32));
+                if matches!(bytes.get(i), Some(b'u' | b'U')) {
+                    i += 1;
+                }
+                continue;
+            }
+            let mut float = false;
+            while i < bytes.len() {
+                let b = bytes[i];
+                if b.is_ascii_digit() {
+                    i += 1;
+                } else if b == b'.' {
+                    float = true;
+                    i += 1;
+                } else if (b == b'e' || b == b'E') && i > start {
+                    float = true;
+                    i += 1;
+                    if matches!(bytes.get(i), Some(b'+' | b'-')) {
+                        i += 1;
+                    }
+                } else {
+                    break;
+                }
+            }
+            let literal = &text[start..i];
+            if matches!(bytes.get(i), Some(b'f' | b'F')) {
+                float = true;
+                i += 1;
+            } else if matches!(bytes.get(i), Some(b'u' | b'U')) {
+                i += 1;
+            }
+            if float {
+                tokens.push(Tok::Float(literal.parse().map_err(|_| err(format!("bad float literal {}", literal)))?));
+            } else {
+                tokens.push(Tok::Int(literal.parse().map_err(|_| err(format!("bad int literal {}", literal)))?));
+            }
+        } else {
+            let punct = PUNCTUATION
+                .iter()
+                .find(|p| text[i..].starts_with(**p))
+                .ok_or_else(|| err(format!("unexpected character '{}'", c)))?;
+            tokens.push(Tok::Punct(punct));
+            i += punct.len();
+        }
+    }
+    Ok(tokens)
+}
+
+// ---------------------------------------------------------------------------
+// Parser
+// ---------------------------------------------------------------------------
+
+struct Parser {
+    tokens: Vec<Tok>,
+    pos: usize,
+    stage: Stage,
+    globals: Vec<Global>,
+    functions: Vec<Function>,
+}
+
+const PRECISION: &[&str] = &["highp", "mediump", "lowp"];
+const IGNORED_QUALIFIERS: &[&str] = &["centroid", "flat", "smooth", "invariant", "highp", "mediump", "lowp"];
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Tok> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn next(&mut self) -> Result<Tok, GpuError> {
+        let tok = self.tokens.get(self.pos).cloned().ok_or_else(|| err("unexpected end of shader"))?;
+        self.pos += 1;
+        Ok(tok)
+    }
+
+    fn is_punct(&self, p: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Punct(q)) if *q == p)
+    }
+
+    fn is_ident(&self, name: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Ident(n)) if n == name)
+    }
+
+    fn eat_punct(&mut self, p: &str) -> bool {
+        if self.is_punct(p) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, p: &str) -> Result<(), GpuError> {
+        if self.eat_punct(p) {
+            Ok(())
+        } else {
+            Err(err(format!("expected '{}' near {:?}", p, self.peek())))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, GpuError> {
+        match self.next()? {
+            Tok::Ident(name) => Ok(name),
+            tok => Err(err(format!("expected identifier, found {:?}", tok))),
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<Type, GpuError> {
+        let name = self.ident()?;
+        match name.as_str() {
+            "struct" => Err(err("structs are not supported")),
+            _ => Type::from_name(&name).ok_or_else(|| err(format!("unknown type {}", name))),
+        }
+    }
+
+    fn array_size(&mut self) -> Result<Option<usize>, GpuError> {
+        if !self.eat_punct("[") {
+            return Ok(None);
+        }
+        let size = match self.next()? {
+            Tok::Int(n) if n > 0 => n as usize,
+            tok => return Err(err(format!("array size must be a positive integer, found {:?}", tok))),
+        };
+        self.expect("]")?;
+        Ok(Some(size))
+    }
+
+    fn parse_translation_unit(&mut self) -> Result<(), GpuError> {
+        while self.peek().is_some() {
+            if self.eat_punct(";") {
+                continue;
+            }
+            if self.is_ident("precision") {
+                while !self.eat_punct(";") {
+                    self.next()?;
+                }
+                continue;
+            }
+            if self.is_ident("invariant") && matches!(self.peek_at(1), Some(Tok::Ident(n)) if n.starts_with("gl_")) {
+                while !self.eat_punct(";") {
+                    self.next()?;
+                }
+                continue;
+            }
+
+            let mut qualifier = Qualifier::None;
+            let mut location = None;
+            while let Some(Tok::Ident(word)) = self.peek().cloned() {
+                match word.as_str() {
+                    "const" => qualifier = Qualifier::Const,
+                    "uniform" => qualifier = Qualifier::Uniform,
+                    "attribute" => qualifier = Qualifier::VertexInput,
+                    "varying" => qualifier = Qualifier::Varying,
+                    "in" => {
+                        qualifier = match self.stage {
+                            Stage::Vertex => Qualifier::VertexInput,
+                            Stage::Fragment => Qualifier::Varying,
+                        }
+                    }
+                    "out" => {
+                        qualifier = match self.stage {
+                            Stage::Vertex => Qualifier::Varying,
+                            Stage::Fragment => Qualifier::FragmentOutput,
+                        }
+                    }
+                    "layout" => {
+                        self.pos += 1;
+                        self.expect("(")?;
+                        while !self.eat_punct(")") {
+                            let key = self.next()?;
+                            let assigns = key == Tok::Ident("location".into()) && self.eat_punct("=");
+                            match self.peek() {
+                                Some(Tok::Int(n)) if assigns => {
+                                    location = Some(*n as u32);
+                                    self.pos += 1;
+                                }
+                                _ => {}
+                            }
+                        }
+                        continue;
+                    }
+                    w if IGNORED_QUALIFIERS.contains(&w) => {}
+                    _ => break,
+                }
+                self.pos += 1;
+            }
+
+            let ty = self.parse_type()?;
+            let name = self.ident()?;
+            if self.is_punct("(") {
+                self.parse_function(ty, name)?;
+                continue;
+            }
+
+            let mut name = name;
+            loop {
+                let array = self.array_size()?;
+                let init = if self.eat_punct("=") { Some(self.parse_assignment()?) } else { None };
+                if qualifier == Qualifier::Const && init.is_none() {
+                    return Err(err(format!("const {} needs an initializer", name)));
+                }
+                self.globals.push(Global { name, ty, array, qualifier, location, init });
+                if self.eat_punct(",") {
+                    name = self.ident()?;
+                } else {
+                    break;
+                }
+            }
+            self.expect(";")?;
+        }
+        Ok(())
+    }
+
+    fn parse_function(&mut self, _ret: Type, name: String) -> Result<(), GpuError> {
+        self.expect("(")?;
+        let mut params = Vec::new();
+        if self.is_ident("void") && matches!(self.peek_at(1), Some(Tok::Punct(")"))) {
+            self.pos += 1;
+        }
+        while !self.eat_punct(")") {
+            let mut mode = ParamMode::In;
+            while let Some(Tok::Ident(word)) = self.peek().cloned() {
+                match word.as_str() {
+                    "in" | "const" => {}
+                    "out" => mode = ParamMode::Out,
+                    "inout" => mode = ParamMode::InOut,
+                    w if PRECISION.contains(&w) => {}
+                    _ => break,
+                }
+                self.pos += 1;
+            }
+            let ty = self.parse_type()?;
+            let param = match self.peek() {
+                Some(Tok::Ident(_)) => self.ident()?,
+                _ => String::new(),
+            };
+            let array = self.array_size()?;
+            params.push((param, ty, array, mode));
+            if !self.eat_punct(",") {
+                self.expect(")")?;
+                break;
+            }
+        }
+        if self.eat_punct(";") {
+            // Prototype
+            return Ok(());
+        }
+        self.expect("{")?;
+        let body = self.parse_block_body()?;
+        self.functions.push(Function { name, params, body });
+        Ok(())
+    }
+
+    fn parse_block_body(&mut self) -> Result<Vec<Stmt>, GpuError> {
+        let mut stmts = Vec::new();
+        while !self.eat_punct("}") {
+            stmts.push(self.parse_statement()?);
+        }
+        Ok(stmts)
+    }
+
+    fn starts_declaration(&self) -> bool {
+        match self.peek() {
+            Some(Tok::Ident(word)) if word == "const" || PRECISION.contains(&word.as_str()) => true,
+            Some(Tok::Ident(word)) if Type::from_name(word).is_some() => {
+                matches!(self.peek_at(1), Some(Tok::Ident(_)))
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_declaration(&mut self) -> Result<Stmt, GpuError> {
+        while let Some(Tok::Ident(word)) = self.peek() {
+            if word == "const" || PRECISION.contains(&word.as_str()) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        let ty = self.parse_type()?;
+        let mut decls = Vec::new();
+        loop {
+            let name = self.ident()?;
+            let array = self.array_size()?;
+            let init = if self.eat_punct("=") { Some(self.parse_assignment()?) } else { None };
+            decls.push(Decl { ty, name, array, init });
+            if !self.eat_punct(",") {
+                break;
+            }
+        }
+        Ok(Stmt::Decls(decls))
+    }
+
+    fn parse_statement(&mut self) -> Result<Stmt, GpuError> {
+        if self.eat_punct("{") {
+            return Ok(Stmt::Block(self.parse_block_body()?));
+        }
+        if self.eat_punct(";") {
+            return Ok(Stmt::Block(Vec::new()));
+        }
+        let keyword = match self.peek() {
+            Some(Tok::Ident(word)) => word.clone(),
+            _ => String::new(),
+        };
+        match keyword.as_str() {
+            "if" => {
+                self.pos += 1;
+                self.expect("(")?;
+                let cond = self.parse_expr()?;
+                self.expect(")")?;
+                let then = Box::new(self.parse_statement()?);
+                let otherwise = if self.is_ident("else") {
+                    self.pos += 1;
+                    Some(Box::new(self.parse_statement()?))
+                } else {
+                    None
+                };
+                Ok(Stmt::If(cond, then, otherwise))
+            }
+            "for" => {
+                self.pos += 1;
+                self.expect("(")?;
+                let init = if self.eat_punct(";") {
+                    None
+                } else {
+                    let stmt = if self.starts_declaration() {
+                        self.parse_declaration()?
+                    } else {
+                        Stmt::Expr(self.parse_expr()?)
+                    };
+                    self.expect(";")?;
+                    Some(Box::new(stmt))
+                };
+                let cond = if self.is_punct(";") { None } else { Some(self.parse_expr()?) };
+                self.expect(";")?;
+                let step = if self.is_punct(")") { None } else { Some(self.parse_expr()?) };
+                self.expect(")")?;
+                let body = Box::new(self.parse_statement()?);
+                Ok(Stmt::For(init, cond, step, body))
+            }
+            "while" => {
+                self.pos += 1;
+                self.expect("(")?;
+                let cond = self.parse_expr()?;
+                self.expect(")")?;
+                Ok(Stmt::While(cond, Box::new(self.parse_statement()?)))
+            }
+            "do" => {
+                self.pos += 1;
+                let body = Box::new(self.parse_statement()?);
+                if !self.is_ident("while") {
+                    return Err(err("expected while after do body"));
+                }
+                self.pos += 1;
+                self.expect("(")?;
+                let cond = self.parse_expr()?;
+                self.expect(")")?;
+                self.expect(";")?;
+                Ok(Stmt::DoWhile(body, cond))
+            }
+            "return" => {
+                self.pos += 1;
+                let value = if self.is_punct(";") { None } else { Some(self.parse_expr()?) };
+                self.expect(";")?;
+                Ok(Stmt::Return(value))
+            }
+            "discard" | "break" | "continue" => {
+                self.pos += 1;
+                self.expect(";")?;
+                Ok(match keyword.as_str() {
+                    "discard" => Stmt::Discard,
+                    "break" => Stmt::Break,
+                    _ => Stmt::Continue,
+                })
+            }
+            _ if self.starts_declaration() => {
+                let stmt = self.parse_declaration()?;
+                self.expect(";")?;
+                Ok(stmt)
+            }
+            _ => {
+                let expr = self.parse_expr()?;
+                self.expect(";")?;
+                Ok(Stmt::Expr(expr))
+            }
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, GpuError> {
+        self.parse_assignment()
+    }
+
+    fn parse_assignment(&mut self) -> Result<Expr, GpuError> {
+        let lhs = self.parse_ternary()?;
+        let op = match self.peek() {
+            Some(Tok::Punct("=")) => None,
+            Some(Tok::Punct("+=")) => Some(BinOp::Add),
+            Some(Tok::Punct("-=")) => Some(BinOp::Sub),
+            Some(Tok::Punct("*=")) => Some(BinOp::Mul),
+            Some(Tok::Punct("/=")) => Some(BinOp::Div),
+            Some(Tok::Punct("%=")) => Some(BinOp::Mod),
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        let rhs = self.parse_assignment()?;
+        Ok(Expr::Assign(op, Box::new(lhs), Box::new(rhs)))
+    }
+
+    fn parse_ternary(&mut self) -> Result<Expr, GpuError> {
+        let cond = self.parse_binary(0)?;
+        if !self.eat_punct("?") {
+            return Ok(cond);
+        }
+        let then = self.parse_assignment()?;
+        self.expect(":")?;
+        let otherwise = self.parse_assignment()?;
+        Ok(Expr::Ternary(Box::new(cond), Box::new(then), Box::new(otherwise)))
+    }
+
+    fn binary_op(&self) -> Option<(BinOp, u8)> {
+        let Some(Tok::Punct(p)) = self.peek() else { return None };
+        Some(match *p {
+            "||" => (BinOp::Or, 1),
+            "^^" => (BinOp::Xor, 2),
+            "&&" => (BinOp::And, 3),
+            "==" => (BinOp::Eq, 4),
+            "!=" => (BinOp::Ne, 4),
+            "<" => (BinOp::Lt, 5),
+            ">" => (BinOp::Gt, 5),
+            "<=" => (BinOp::Le, 5),
+            ">=" => (BinOp::Ge, 5),
+            "+" => (BinOp::Add, 6),
+            "-" => (BinOp::Sub, 6),
+            "*" => (BinOp::Mul, 7),
+            "/" => (BinOp::Div, 7),
+            "%" => (BinOp::Mod, 7),
+            _ => return None,
+        })
+    }
+
+    fn parse_binary(&mut self, min: u8) -> Result<Expr, GpuError> {
+        let mut lhs = self.parse_unary()?;
+        while let Some((op, prec)) = self.binary_op() {
+            if prec < min {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.parse_binary(prec + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, GpuError> {
+        let op = match self.peek() {
+            Some(Tok::Punct("-")) => Some(UnOp::Neg),
+            Some(Tok::Punct("!")) => Some(UnOp::Not),
+            Some(Tok::Punct("++")) => Some(UnOp::PreInc),
+            Some(Tok::Punct("--")) => Some(UnOp::PreDec),
+            Some(Tok::Punct("+")) => {
+                self.pos += 1;
+                return self.parse_unary();
+            }
+            _ => None,
+        };
+        match op {
+            Some(op) => {
+                self.pos += 1;
+                Ok(Expr::Unary(op, Box::new(self.parse_unary()?)))
+            }
+            None => self.parse_postfix(),
+        }
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr, GpuError> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            if self.eat_punct(".") {
+                expr = Expr::Field(Box::new(expr), self.ident()?);
+            } else if self.eat_punct("[") {
+                let index = self.parse_expr()?;
+                self.expect("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else if self.eat_punct("++") {
+                expr = Expr::Unary(UnOp::PostInc, Box::new(expr));
+            } else if self.eat_punct("--") {
+                expr = Expr::Unary(UnOp::PostDec, Box::new(expr));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, GpuError> {
+        match self.next()? {
+            Tok::Float(f) => Ok(Expr::Lit(Value::Float(f))),
+            Tok::Int(i) => Ok(Expr::Lit(Value::Int(i))),
+            Tok::Punct("(") => {
+                let expr = self.parse_expr()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Tok::Ident(name) if name == "true" => Ok(Expr::Lit(Value::Bool(true))),
+            Tok::Ident(name) if name == "false" => Ok(Expr::Lit(Value::Bool(false))),
+            Tok::Ident(name) => {
+                if self.eat_punct("(") {
+                    let mut args = Vec::new();
+                    if self.is_ident("void") {
+                        self.pos += 1;
+                    }
+                    while !self.eat_punct(")") {
+                        args.push(self.parse_assignment()?);
+                        if !self.eat_punct(",") {
+                            self.expect(")")?;
+                            break;
+                        }
+                    }
+                    Ok(Expr::Call(name, args))
+                } else {
+                    Ok(Expr::Var(name))
+                }
+            }
+            tok => Err(err(format!("unexpected token {:?}", tok))),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Execution
+// ---------------------------------------------------------------------------
+
+/// Texture lookups for `texture2D()` and `texture()`
+pub trait TextureSampler {
+    /// Sample the texture bound to `unit` at normalized coordinates
+    fn sample(&self, unit: i32, s: f32, t: f32) -> [f32; 4];
+}
+
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+    Discard,
+}
+
+/// Upper bound on loop iterations per invocation
+const MAX_ITERATIONS: usize = 1 << 16;
+
+/// Runs a module's `main()` with persistent uniforms
+pub struct Executor<'a> {
+    module: &'a ShaderModule,
+    sampler: &'a dyn TextureSampler,
+    globals: HashMap<String, Value>,
+    scopes: Vec<HashMap<String, Value>>,
+    iterations: usize,
+}
+
+impl<'a> Executor<'a> {
+    pub fn new(module: &'a ShaderModule, sampler: &'a dyn TextureSampler) -> Self {
+        let mut globals = HashMap::new();
+        for global in &module.globals {
+            globals.insert(global.name.clone(), global.zero());
+        }
+        let builtins: &[(&str, Value)] = match module.stage {
+            Stage::Vertex => &[
+                ("gl_Position", Value::Vec(4, [0.0; 4])),
+                ("gl_PointSize", Value::Float(1.0)),
+                ("gl_VertexID", Value::Int(0)),
+                ("gl_InstanceID", Value::Int(0)),
+            ],
+            Stage::Fragment => &[
+                ("gl_FragCoord", Value::Vec(4, [0.0; 4])),
+                ("gl_FrontFacing", Value::Bool(true)),
+                ("gl_PointCoord", Value::Vec(2, [0.0; 4])),
+                ("gl_FragColor", Value::Vec(4, [0.0; 4])),
+            ],
+        };
+        for (name, value) in builtins {
+            globals.insert(name.to_string(), value.clone());
+        }
+        if module.stage == Stage::Fragment {
+            globals.insert("gl_FragData".into(), Value::Array(vec![Value::Vec(4, [0.0; 4])]));
+        }
+        Self { module, sampler, globals, scopes: Vec::new(), iterations: 0 }
+    }
+
+    /// Set a uniform, input or builtin
+    pub fn set(&mut self, name: &str, value: Value) {
+        if let Some(slot) = self.globals.get_mut(name) {
+            *slot = value;
+        }
+    }
+
+    /// Read a global after `run`
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    /// Run `main()`; returns false if the fragment was discarded
+    pub fn run(&mut self) -> Result<bool, GpuError> {
+        self.iterations = 0;
+        self.scopes.clear();
+        for global in &self.module.globals {
+            if matches!(global.qualifier, Qualifier::None | Qualifier::Const) {
+                let value = match &global.init {
+                    Some(init) => self.eval(init)?,
+                    None => global.zero(),
+                };
+                self.globals.insert(global.name.clone(), value);
+            }
+        }
+        let main = self
+            .module
+            .functions
+            .iter()
+            .find(|f| f.name == "main" && f.params.is_empty())
+            .ok_or_else(|| err("missing main()"))?;
+        self.scopes.push(HashMap::new());
+        let flow = self.exec_block(&main.body)?;
+        self.scopes.pop();
+        Ok(!matches!(flow, Flow::Discard))
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.scopes.iter().rev().find_map(|s| s.get(name)).or_else(|| self.globals.get(name))
+    }
+
+    fn store(&mut self, name: &str, value: Value) -> Result<(), GpuError> {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(slot) = scope.get_mut(name) {
+                *slot = value;
+                return Ok(());
+            }
+        }
+        match self.globals.get_mut(name) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => Err(err(format!("undeclared identifier {}", name))),
+        }
+    }
+
+    fn exec_block(&mut self, stmts: &[Stmt]) -> Result<Flow, GpuError> {
+        for stmt in stmts {
+            match self.exec(stmt)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self) -> Result<Flow, GpuError>) -> Result<Flow, GpuError> {
+        self.scopes.push(HashMap::new());
+        let flow = f(self);
+        self.scopes.pop();
+        flow
+    }
+
+    fn tick(&mut self) -> Result<(), GpuError> {
+        self.iterations += 1;
+        if self.iterations > MAX_ITERATIONS {
+            return Err(err("loop iteration limit exceeded"));
+        }
+        Ok(())
+    }
+
+    fn exec(&mut self, stmt: &Stmt) -> Result<Flow, GpuError> {
+        match stmt {
+            Stmt::Decls(decls) => {
+                for decl in decls {
+                    let value = match (&decl.init, decl.array) {
+                        (Some(init), _) => self.eval(init)?,
+                        (None, Some(n)) => Value::Array(vec![decl.ty.zero(); n]),
+                        (None, None) => decl.ty.zero(),
+                    };
+                    let value = coerce(value, decl.ty);
+                    if let Some(scope) = self.scopes.last_mut() {
+                        scope.insert(decl.name.clone(), value);
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            Stmt::Expr(expr) => {
+                self.eval(expr)?;
+                Ok(Flow::Normal)
+            }
+            Stmt::If(cond, then, otherwise) => {
+                if self.eval(cond)?.as_bool() {
+                    self.scoped(|s| s.exec(then))
+                } else if let Some(otherwise) = otherwise {
+                    self.scoped(|s| s.exec(otherwise))
+                } else {
+                    Ok(Flow::Normal)
+                }
+            }
+            Stmt::For(init, cond, step, body) => self.scoped(|s| {
+                if let Some(init) = init {
+                    s.exec(init)?;
+                }
+                loop {
+                    s.tick()?;
+                    let done = match cond {
+                        Some(cond) => !s.eval(cond)?.as_bool(),
+                        None => false,
+                    };
+                    if done {
+                        break;
+                    }
+                    match s.scoped(|s| s.exec(body))? {
+                        Flow::Break => break,
+                        flow @ (Flow::Return(_) | Flow::Discard) => return Ok(flow),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                    if let Some(step) = step {
+                        s.eval(step)?;
+                    }
+                }
+                Ok(Flow::Normal)
+            }),
+            Stmt::While(cond, body) => {
+                while self.eval(cond)?.as_bool() {
+                    self.tick()?;
+                    match self.scoped(|s| s.exec(body))? {
+                        Flow::Break => break,
+                        flow @ (Flow::Return(_) | Flow::Discard) => return Ok(flow),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            Stmt::DoWhile(body, cond) => {
+                loop {
+                    self.tick()?;
+                    match self.scoped(|s| s.exec(body))? {
+                        Flow::Break => break,
+                        flow @ (Flow::Return(_) | Flow::Discard) => return Ok(flow),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                    if !self.eval(cond)?.as_bool() {
+                        break;
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(expr) => self.eval(expr)?,
+                    None => Value::Void,
+                };
+                Ok(Flow::Return(value))
+            }
+            Stmt::Discard => Ok(Flow::Discard),
+            Stmt::Break => Ok(Flow::Break),
+            Stmt::Continue => Ok(Flow::Continue),
+            Stmt::Block(stmts) => self.scoped(|s| s.exec_block(stmts)),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value, GpuError> {
+        match expr {
+            Expr::Lit(value) => Ok(value.clone()),
+            Expr::Var(name) => self.lookup(name).cloned().ok_or_else(|| err(format!("undeclared identifier {}", name))),
+            Expr::Unary(op, operand) => {
+                let value = self.eval(operand)?;
+                match op {
+                    UnOp::Neg => Ok(map1(&value, |x| -x)),
+                    UnOp::Not => Ok(Value::Bool(!value.as_bool())),
+                    UnOp::PreInc | UnOp::PreDec | UnOp::PostInc | UnOp::PostDec => {
+                        let delta = if matches!(op, UnOp::PreInc | UnOp::PostInc) { 1.0 } else { -1.0 };
+                        let updated = map1(&value, |x| x + delta);
+                        self.assign(operand, updated.clone())?;
+                        Ok(if matches!(op, UnOp::PreInc | UnOp::PreDec) { updated } else { value })
+                    }
+                }
+            }
+            Expr::Binary(BinOp::And, a, b) => {
+                Ok(Value::Bool(self.eval(a)?.as_bool() && self.eval(b)?.as_bool()))
+            }
+            Expr::Binary(BinOp::Or, a, b) => {
+                Ok(Value::Bool(self.eval(a)?.as_bool() || self.eval(b)?.as_bool()))
+            }
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (self.eval(a)?, self.eval(b)?);
+                binary(*op, &a, &b)
+            }
+            Expr::Assign(op, target, value) => {
+                let mut value = self.eval(value)?;
+                if let Some(op) = op {
+                    value = binary(*op, &self.eval(target)?, &value)?;
+                }
+                self.assign(target, value.clone())?;
+                Ok(value)
+            }
+            Expr::Ternary(cond, then, otherwise) => {
+                if self.eval(cond)?.as_bool() {
+                    self.eval(then)
+                } else {
+                    self.eval(otherwise)
+                }
+            }
+            Expr::Field(base, field) => {
+                let base = self.eval(base)?;
+                swizzle(&base, field)
+            }
+            Expr::Index(base, index) => {
+                let base = self.eval(base)?;
+                let index = self.eval(index)?.as_i32();
+                element(&base, index)
+            }
+            Expr::Call(name, args) => self.call(name, args),
+        }
+    }
+
+    fn assign(&mut self, target: &Expr, value: Value) -> Result<(), GpuError> {
+        match target {
+            Expr::Var(name) => {
+                let value = match self.lookup(name) {
+                    Some(Value::Int(_)) => Value::Int(value.as_i32()),
+                    Some(Value::Float(_)) => Value::Float(value.as_f32()),
+                    Some(Value::Bool(_)) => Value::Bool(value.as_bool()),
+                    _ => value,
+                };
+                self.store(name, value)
+            }
+            Expr::Field(base, field) => {
+                let mut current = self.eval(base)?;
+                write_swizzle(&mut current, field, &value)?;
+                self.assign(base, current)
+            }
+            Expr::Index(base, index) => {
+                let mut current = self.eval(base)?;
+                let index = self.eval(index)?.as_i32();
+                write_element(&mut current, index, value)?;
+                self.assign(base, current)
+            }
+            _ => Err(err("assignment to a non-lvalue")),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr]) -> Result<Value, GpuError> {
+        let module = self.module;
+        if let Some(function) = module.functions.iter().find(|f| f.name == name && f.params.len() == args.len()) {
+            let mut locals = HashMap::new();
+            for ((param, ty, array, mode), arg) in function.params.iter().zip(args) {
+                let value = match mode {
+                    ParamMode::Out => match array {
+                        Some(n) => Value::Array(vec![ty.zero(); *n]),
+                        None => ty.zero(),
+                    },
+                    _ => coerce(self.eval(arg)?, *ty),
+                };
+                locals.insert(param.clone(), value);
+            }
+            // Callee sees only globals and its own parameters
+            let saved = std::mem::replace(&mut self.scopes, vec![locals]);
+            let flow = self.exec_block(&function.body);
+            let frame = std::mem::replace(&mut self.scopes, saved);
+            let result = match flow? {
+                Flow::Return(value) => value,
+                Flow::Discard => return Err(err("discard inside a function is not supported")),
+                _ => Value::Void,
+            };
+            let outputs = function.params.iter().zip(args).filter(|((_, _, _, mode), _)| *mode != ParamMode::In);
+            for ((param, _, _, _), arg) in outputs {
+                if let Some(value) = frame[0].get(param).cloned() {
+                    self.assign(arg, value)?;
+                }
+            }
+            return Ok(result);
+        }
+
+        let values = args.iter().map(|a| self.eval(a)).collect::<Result<Vec<_>, _>>()?;
+        if let Some(ty) = Type::from_name(name) {
+            return Ok(construct(ty, &values));
+        }
+        builtin(name, &values, self.sampler)
+    }
+}
+
+/// Convert a value to a declared type where GLSL allows it implicitly
+fn coerce(value: Value, ty: Type) -> Value {
+    match (ty, &value) {
+        (Type::Float, Value::Int(i)) => Value::Float(*i as f32),
+        (Type::Int, Value::Float(f)) => Value::Int(*f as i32),
+        _ => value,
+    }
+}
+
+fn construct(ty: Type, args: &[Value]) -> Value {
+    match (ty, args) {
+        (Type::Vec(n), [single]) if !matches!(single, Value::Vec(..) | Value::Mat(..)) => {
+            let x = single.as_f32();
+            let mut v = [0.0; 4];
+            v[..n as usize].fill(x);
+            Value::Vec(n, v)
+        }
+        (Type::Mat(n), [Value::Mat(m, src)]) => {
+            let mut out = identity(n);
+            for c in 0..n.min(*m) as usize {
+                for r in 0..n.min(*m) as usize {
+                    out[c * 4 + r] = src[c * 4 + r];
+                }
+            }
+            Value::Mat(n, out)
+        }
+        (Type::Mat(n), [single]) if !matches!(single, Value::Vec(..)) => {
+            let x = single.as_f32();
+            let mut out = [0.0; 16];
+            for i in 0..n as usize {
+                out[i * 4 + i] = x;
+            }
+            Value::Mat(n, out)
+        }
+        _ => {
+            let mut data = Vec::with_capacity(16);
+            for arg in args {
+                arg.flatten(&mut data);
+            }
+            Value::from_components(ty, &data)
+        }
+    }
+}
+
+fn identity(n: u8) -> [f32; 16] {
+    let mut m = [0.0; 16];
+    for i in 0..n as usize {
+        m[i * 4 + i] = 1.0;
+    }
+    m
+}
+
+fn swizzle_index(c: char) -> Option<usize> {
+    Some(match c {
+        'x' | 'r' | 's' => 0,
+        'y' | 'g' | 't' => 1,
+        'z' | 'b' | 'p' => 2,
+        'w' | 'a' | 'q' => 3,
+        _ => return None,
+    })
+}
+
+fn swizzle(base: &Value, field: &str) -> Result<Value, GpuError> {
+    let (n, v) = match base {
+        Value::Vec(n, v) => (*n as usize, *v),
+        Value::Float(_) | Value::Int(_) | Value::Bool(_) => (1, [base.as_f32(), 0.0, 0.0, 0.0]),
+        _ => return Err(err(format!("cannot select .{} here", field))),
+    };
+    if field == "length" {
+        return Err(err(".length() is not supported"));
+    }
+    let mut out = [0.0; 4];
+    let mut count = 0;
+    for c in field.chars() {
+        let i = swizzle_index(c).filter(|i| *i < n).ok_or_else(|| err(format!("bad swizzle .{}", field)))?;
+        if count == 4 {
+            return Err(err(format!("bad swizzle .{}", field)));
+        }
+        out[count] = v[i];
+        count += 1;
+    }
+    Ok(if count == 1 { Value::Float(out[0]) } else { Value::Vec(count as u8, out) })
+}
+
+fn write_swizzle(target: &mut Value, field: &str, value: &Value) -> Result<(), GpuError> {
+    let src = match value {
+        Value::Vec(_, v) => *v,
+        other => [other.as_f32(); 4],
+    };
+    match target {
+        Value::Vec(n, v) => {
+            for (k, c) in field.chars().enumerate() {
+                let i = swizzle_index(c).filter(|i| *i < *n as usize).ok_or_else(|| err(format!("bad swizzle .{}", field)))?;
+                v[i] = src[k.min(3)];
+            }
+            Ok(())
+        }
+        Value::Float(f) if field.len() == 1 => {
+            *f = src[0];
+            Ok(())
+        }
+        _ => Err(err(format!("cannot assign .{}", field))),
+    }
+}
+
+fn element(base: &Value, index: i32) -> Result<Value, GpuError> {
+    let i = index.max(0) as usize;
+    match base {
+        Value::Vec(n, v) if i < *n as usize => Ok(Value::Float(v[i])),
+        Value::Mat(n, m) if i < *n as usize => {
+            let mut col = [0.0; 4];
+            col[..*n as usize].copy_from_slice(&m[i * 4..i * 4 + *n as usize]);
+            Ok(Value::Vec(*n, col))
+        }
+        Value::Array(items) => items.get(i).cloned().ok_or_else(|| err(format!("index {} out of range", index))),
+        _ => Err(err(format!("index {} out of range", index))),
+    }
+}
+
+fn write_element(target: &mut Value, index: i32, value: Value) -> Result<(), GpuError> {
+    let i = index.max(0) as usize;
+    match target {
+        Value::Vec(n, v) if i < *n as usize => v[i] = value.as_f32(),
+        Value::Mat(n, m) if i < *n as usize => {
+            let col = value.as_vec4();
+            m[i * 4..i * 4 + *n as usize].copy_from_slice(&col[..*n as usize]);
+        }
+        Value::Array(items) if i < items.len() => items[i] = value,
+        _ => return Err(err(format!("index {} out of range", index))),
+    }
+    Ok(())
+}
+
+fn map1(v: &Value, f: impl Fn(f32) -> f32) -> Value {
+    match v {
+        Value::Int(i) => Value::Int(f(*i as f32) as i32),
+        Value::Float(x) => Value::Float(f(*x)),
+        Value::Vec(n, a) => Value::Vec(*n, a.map(&f)),
+        Value::Mat(n, m) => Value::Mat(*n, m.map(&f)),
+        Value::Bool(b) => Value::Float(f(*b as i32 as f32)),
+        other => other.clone(),
+    }
+}
+
+/// Component-wise operation with scalar broadcasting
+fn map2(a: &Value, b: &Value, f: impl Fn(f32, f32) -> f32) -> Value {
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => Value::Int(f(*x as f32, *y as f32) as i32),
+        (Value::Vec(n, x), Value::Vec(_, y)) => Value::Vec(*n, std::array::from_fn(|i| f(x[i], y[i]))),
+        (Value::Vec(n, x), s) => {
+            let s = s.as_f32();
+            Value::Vec(*n, x.map(|x| f(x, s)))
+        }
+        (s, Value::Vec(n, y)) => {
+            let s = s.as_f32();
+            Value::Vec(*n, y.map(|y| f(s, y)))
+        }
+        (Value::Mat(n, x), Value::Mat(_, y)) => Value::Mat(*n, std::array::from_fn(|i| f(x[i], y[i]))),
+        (Value::Mat(n, x), s) => {
+            let s = s.as_f32();
+            Value::Mat(*n, x.map(|x| f(x, s)))
+        }
+        (s, Value::Mat(n, y)) => {
+            let s = s.as_f32();
+            Value::Mat(*n, y.map(|y| f(s, y)))
+        }
+        (x, y) => Value::Float(f(x.as_f32(), y.as_f32())),
+    }
+}
+
+fn map3(a: &Value, b: &Value, c: &Value, f: impl Fn(f32, f32, f32) -> f32) -> Value {
+    let lane = |v: &Value, i: usize| match v {
+        Value::Vec(_, x) => x[i],
+        other => other.as_f32(),
+    };
+    let n = [a, b, c].iter().find_map(|v| match v {
+        Value::Vec(n, _) => Some(*n),
+        _ => None,
+    });
+    match n {
+        Some(n) => Value::Vec(n, std::array::from_fn(|i| f(lane(a, i), lane(b, i), lane(c, i)))),
+        None => Value::Float(f(a.as_f32(), b.as_f32(), c.as_f32())),
+    }
+}
+
+fn binary(op: BinOp, a: &Value, b: &Value) -> Result<Value, GpuError> {
+    Ok(match op {
+        BinOp::Add => map2(a, b, |x, y| x + y),
+        BinOp::Sub => map2(a, b, |x, y| x - y),
+        BinOp::Mul => match (a, b) {
+            (Value::Mat(n, m), Value::Vec(_, v)) => {
+                let n = *n as usize;
+                let mut out = [0.0; 4];
+                for (r, o) in out.iter_mut().enumerate().take(n) {
+                    *o = (0..n).map(|c| m[c * 4 + r] * v[c]).sum();
+                }
+                Value::Vec(n as u8, out)
+            }
+            (Value::Vec(_, v), Value::Mat(n, m)) => {
+                let n = *n as usize;
+                let mut out = [0.0; 4];
+                for (c, o) in out.iter_mut().enumerate().take(n) {
+                    *o = (0..n).map(|r| v[r] * m[c * 4 + r]).sum();
+                }
+                Value::Vec(n as u8, out)
+            }
+            (Value::Mat(n, x), Value::Mat(_, y)) => {
+                let n = *n as usize;
+                let mut out = [0.0; 16];
+                for c in 0..n {
+                    for r in 0..n {
+                        out[c * 4 + r] = (0..n).map(|k| x[k * 4 + r] * y[c * 4 + k]).sum();
+                    }
+                }
+                Value::Mat(n as u8, out)
+            }
+            _ => map2(a, b, |x, y| x * y),
+        },
+        BinOp::Div => match (a, b) {
+            (Value::Int(x), Value::Int(y)) => Value::Int(if *y == 0 { 0 } else { x.wrapping_div(*y) }),
+            _ => map2(a, b, |x, y| x / y),
+        },
+        BinOp::Mod => match (a, b) {
+            (Value::Int(x), Value::Int(y)) => Value::Int(if *y == 0 { 0 } else { x.wrapping_rem(*y) }),
+            _ => map2(a, b, |x, y| x - y * (x / y).floor()),
+        },
+        BinOp::Lt => Value::Bool(a.as_f32() < b.as_f32()),
+        BinOp::Gt => Value::Bool(a.as_f32() > b.as_f32()),
+        BinOp::Le => Value::Bool(a.as_f32() <= b.as_f32()),
+        BinOp::Ge => Value::Bool(a.as_f32() >= b.as_f32()),
+        BinOp::Eq => Value::Bool(equal(a, b)),
+        BinOp::Ne => Value::Bool(!equal(a, b)),
+        BinOp::Xor => Value::Bool(a.as_bool() != b.as_bool()),
+        BinOp::And => Value::Bool(a.as_bool() && b.as_bool()),
+        BinOp::Or => Value::Bool(a.as_bool() || b.as_bool()),
+    })
+}
+
+fn equal(a: &Value, b: &Value) -> bool {
+    let (mut x, mut y) = (Vec::new(), Vec::new());
+    a.flatten(&mut x);
+    b.flatten(&mut y);
+    x == y
+}
+
+fn dot(a: &Value, b: &Value) -> f32 {
+    let (x, y) = (a.as_vec4(), b.as_vec4());
+    match (a, b) {
+        (Value::Vec(n, _), _) | (_, Value::Vec(n, _)) => (0..*n as usize).map(|i| x[i] * y[i]).sum(),
+        _ => a.as_f32() * b.as_f32(),
+    }
+}
+
+fn builtin(name: &str, args: &[Value], sampler: &dyn TextureSampler) -> Result<Value, GpuError> {
+    let arg = |i: usize| args.get(i).ok_or_else(|| err(format!("{}() expects more arguments", name)));
+    Ok(match name {
+        "radians" => map1(arg(0)?, f32::to_radians),
+        "degrees" => map1(arg(0)?, f32::to_degrees),
+        "sin" => map1(arg(0)?, f32::sin),
+        "cos" => map1(arg(0)?, f32::cos),
+        "tan" => map1(arg(0)?, f32::tan),
+        "asin" => map1(arg(0)?, f32::asin),
+        "acos" => map1(arg(0)?, f32::acos),
+        "atan" if args.len() == 2 => map2(arg(0)?, arg(1)?, f32::atan2),
+        "atan" => map1(arg(0)?, f32::atan),
+        "pow" => map2(arg(0)?, arg(1)?, f32::powf),
+        "exp" => map1(arg(0)?, f32::exp),
+        "log" => map1(arg(0)?, f32::ln),
+        "exp2" => map1(arg(0)?, f32::exp2),
+        "log2" => map1(arg(0)?, f32::log2),
+        "sqrt" => map1(arg(0)?, f32::sqrt),
+        "inversesqrt" => map1(arg(0)?, |x| 1.0 / x.sqrt()),
+        "abs" => map1(arg(0)?, f32::abs),
+        "sign" => map1(arg(0)?, |x| if x > 0.0 { 1.0 } else if x < 0.0 { -1.0 } else { 0.0 }),
+        "floor" => map1(arg(0)?, f32::floor),
+        "ceil" => map1(arg(0)?, f32::ceil),
+        "round" => map1(arg(0)?, f32::round),
+        "trunc" => map1(arg(0)?, f32::trunc),
+        "fract" => map1(arg(0)?, |x| x - x.floor()),
+        "mod" => map2(arg(0)?, arg(1)?, |x, y| x - y * (x / y).floor()),
+        "min" => map2(arg(0)?, arg(1)?, f32::min),
+        "max" => map2(arg(0)?, arg(1)?, f32::max),
+        "clamp" => map3(arg(0)?, arg(1)?, arg(2)?, |x, lo, hi| x.max(lo).min(hi)),
+        "mix" => match arg(2)? {
+            Value::Bool(t) => if *t { arg(1)?.clone() } else { arg(0)?.clone() },
+            t => map3(arg(0)?, arg(1)?, t, |x, y, a| x * (1.0 - a) + y * a),
+        },
+        "step" => map2(arg(0)?, arg(1)?, |edge, x| if x < edge { 0.0 } else { 1.0 }),
+        "smoothstep" => map3(arg(0)?, arg(1)?, arg(2)?, |e0, e1, x| {
+            let t = ((x - e0) / (e1 - e0)).clamp(0.0, 1.0);
+            t * t * (3.0 - 2.0 * t)
+        }),
+        "length" => Value::Float(dot(arg(0)?, arg(0)?).sqrt()),
+        "distance" => {
+            let d = map2(arg(0)?, arg(1)?, |x, y| x - y);
+            Value::Float(dot(&d, &d).sqrt())
+        }
+        "dot" => Value::Float(dot(arg(0)?, arg(1)?)),
+        "cross" => {
+            let (a, b) = (arg(0)?.as_vec4(), arg(1)?.as_vec4());
+            Value::Vec(3, [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0], 0.0])
+        }
+        "normalize" => {
+            let v = arg(0)?;
+            let len = dot(v, v).sqrt();
+            map1(v, |x| if len > 0.0 { x / len } else { 0.0 })
+        }
+        "reflect" => {
+            let (i, n) = (arg(0)?, arg(1)?);
+            let d = 2.0 * dot(n, i);
+            map2(i, n, |i, n| i - d * n)
+        }
+        "faceforward" => {
+            if dot(arg(2)?, arg(1)?) < 0.0 { arg(0)?.clone() } else { map1(arg(0)?, |x| -x) }
+        }
+        "transpose" => match arg(0)? {
+            Value::Mat(n, m) => {
+                let mut out = [0.0; 16];
+                for c in 0..*n as usize {
+                    for r in 0..*n as usize {
+                        out[r * 4 + c] = m[c * 4 + r];
+                    }
+                }
+                Value::Mat(*n, out)
+            }
+            other => other.clone(),
+        },
+        "matrixCompMult" => map2(arg(0)?, arg(1)?, |x, y| x * y),
+        "texture2D" | "texture" | "texture2DLod" | "textureLod" | "texture2DProj" | "textureProj" => {
+            let mut uv = arg(1)?.as_vec4();
+            if name.ends_with("Proj") {
+                let q = match arg(1)? {
+                    Value::Vec(3, v) => v[2],
+                    _ => uv[3],
+                };
+                uv = [uv[0] / q, uv[1] / q, 0.0, 0.0];
+            }
+            Value::Vec(4, sampler.sample(arg(0)?.as_i32(), uv[0], uv[1]))
+        }
+        _ => return Err(err(format!("unknown function {}()", name))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Checker;
+
+    impl TextureSampler for Checker {
+        fn sample(&self, unit: i32, s: f32, t: f32) -> [f32; 4] {
+            [s, t, unit as f32, 1.0]
+        }
+    }
+
+    #[test]
+    fn test_translate_and_run() {
+        let source = r#"
+            #ifdef GL_ES
+            precision mediump float;
+            #endif
+            #define SCALE 2.0
+            uniform mat4 u_matrix;
+            uniform vec4 u_colors[2];
+            attribute vec2 a_position;
+            varying vec3 v_color;
+
+            float twice(float x) { return x * SCALE; }
+            void split(in vec2 p, out float x, inout float acc) { x = p.x; acc += p.y; }
+
+            void main() {
+                float acc = 1.0;
+                float x;
+                split(a_position, x, acc);
+                vec4 sum = vec4(0.0);
+                for (int i = 0; i < 2; i++) {
+                    sum += u_colors[i];
+                }
+                v_color = sum.rgb * 0.5;
+                v_color.b = twice(acc);
+                gl_Position = u_matrix * vec4(a_position, 0.0, 1.0);
+                gl_Position.xy += x > 0.5 ? vec2(1.0) : vec2(0.0);
+            }
+        "#;
+        let module = translate(source, Stage::Vertex).unwrap();
+        let names: Vec<_> = module.globals_with(Qualifier::VertexInput).map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["a_position"]);
+
+        let mut exec = Executor::new(&module, &Checker);
+        let mut matrix = identity(4);
+        matrix[12] = 10.0; // translate x
+        exec.set("u_matrix", Value::Mat(4, matrix));
+        exec.set("u_colors", Value::Array(vec![Value::Vec(4, [1.0, 0.0, 0.0, 1.0]), Value::Vec(4, [0.0, 1.0, 0.0, 1.0])]));
+        exec.set("a_position", Value::Vec(2, [1.0, 2.0, 0.0, 0.0]));
+        assert!(exec.run().unwrap());
+        assert_eq!(exec.get("gl_Position"), Some(&Value::Vec(4, [12.0, 3.0, 0.0, 1.0])));
+        assert_eq!(exec.get("v_color"), Some(&Value::Vec(3, [0.5, 0.5, 6.0, 0.0])));
+    }
+
+    #[test]
+    fn test_fragment_features() {
+        let source = r#"#version 300 es
+            precision highp float;
+            uniform sampler2D u_tex;
+            in vec2 v_uv;
+            layout(location = 0) out vec4 outColor;
+            void main() {
+                if (v_uv.x < 0.0) discard;
+                outColor = texture(u_tex, v_uv.yx);
+            }
+        "#;
+        let module = translate(source, Stage::Fragment).unwrap();
+        assert_eq!(module.version, 300);
+        let output = module.globals_with(Qualifier::FragmentOutput).next().unwrap();
+        assert_eq!((output.name.as_str(), output.location), ("outColor", Some(0)));
+
+        let mut exec = Executor::new(&module, &Checker);
+        exec.set("u_tex", Value::Sampler(3));
+        exec.set("v_uv", Value::Vec(2, [0.25, 0.75, 0.0, 0.0]));
+        assert!(exec.run().unwrap());
+        assert_eq!(exec.get("outColor"), Some(&Value::Vec(4, [0.75, 0.25, 3.0, 1.0])));
+        exec.set("v_uv", Value::Vec(2, [-1.0, 0.0, 0.0, 0.0]));
+        assert!(!exec.run().unwrap());
+
+        assert!(translate("void main() { undefined_call(); }", Stage::Fragment).is_ok());
+        assert!(translate("void helper() {}", Stage::Fragment).is_err());
+        assert!(translate("void main() { float x = ; }", Stage::Fragment).is_err());
+    }
+}
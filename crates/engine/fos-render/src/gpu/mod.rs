@@ -2,6 +2,12 @@
 //!
 //! Optional GPU-accelerated rendering backend using wgpu.
 //! This module provides hardware-accelerated rendering when available.
+//! The `gl` device executes WebGL draw calls with shaders translated by `glsl`.
+
+pub mod gl;
+pub mod glsl;
+
+pub use gl::GlDevice;
 
 /// GPU context state
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -59,6 +59,25 @@ impl Frame {
         }
     }
     
+    /// Frame holding a picture of the AV1 decoder
+    pub fn from_picture(picture: &fos_av1::Picture) -> Self {
+        let (ss_x, ss_y) = (picture.ss_x as usize, picture.ss_y as usize);
+        let (width, height) = (picture.width as usize, picture.height as usize);
+        let planes = picture.planes.iter().enumerate().map(|(i, samples)| {
+            let (w, h) = if i == 0 { (width, height) } else { ((width + ss_x) >> ss_x, (height + ss_y) >> ss_y) };
+            Plane { data: samples.iter().map(|&v| v as i16).collect(), width: w, height: h, stride: w }
+        }).collect::<Vec<_>>();
+        Self {
+            width: picture.width,
+            height: picture.height,
+            bit_depth: picture.bit_depth,
+            subsampling_x: ss_x as u8,
+            subsampling_y: ss_y as u8,
+            monochrome: planes.len() == 1,
+            planes,
+        }
+    }
+    
     /// Number of planes
     pub fn num_planes(&self) -> usize {
         if self.monochrome { 1 } else { 3 }
@@ -1,15 +1,10 @@
 //! AVIF Decoder
 //!
 //! From-scratch AVIF (AV1 Image File Format) decoder.
-//! Parses ISOBMFF container and decodes AV1 intra-frames.
+//! Parses ISOBMFF container and decodes the AV1 image items with the
+//! `fos-av1` decoder that video playback uses.
 
-mod bitreader;
 mod container;
-mod obu;
-mod transform;
-mod predict;
-mod entropy;
-mod filter;
 mod frame;
 mod color;
 
@@ -91,36 +86,13 @@ impl AvifDecoder {
         let container = container::parse_container(data)?;
         
         // Extract AV1 coded data
-        let av1_data = &data[container.data_offset..container.data_offset + container.data_size];
+        let av1_data = data.get(container.data_offset..container.data_offset + container.data_size)
+            .ok_or(AvifError::InvalidData)?;
         
-        // Phase 2: Parse AV1 OBUs
-        let (seq_header, frame_header, tile_data) = obu::parse_obus(av1_data)?;
+        // Phase 2: Decode the AV1 key frame
+        let frame = decode_av1(av1_data)?;
         
-        // Phase 3: Decode intra-frame
-        let mut frame = frame::Frame::new(
-            container.width,
-            container.height,
-            seq_header.bit_depth,
-            seq_header.subsampling_x,
-            seq_header.subsampling_y,
-            seq_header.monochrome,
-        );
-        
-        // Decode tiles
-        self.decode_tiles(&mut frame, &tile_data, &seq_header, &frame_header)?;
-        
-        // Apply loop filters
-        if frame_header.loop_filter.enabled {
-            filter::deblock_filter(&mut frame, &frame_header.loop_filter);
-        }
-        if frame_header.cdef.enabled {
-            filter::cdef_filter(&mut frame, &frame_header.cdef);
-        }
-        if frame_header.restoration.enabled {
-            filter::loop_restoration(&mut frame, &frame_header.restoration);
-        }
-        
-        // Phase 4: Color conversion
+        // Phase 3: Color conversion
         let color_info = ColorInfo {
             primaries: container.color_primaries,
             transfer: container.transfer_characteristics,
@@ -139,295 +111,31 @@ impl AvifDecoder {
         };
         
         Ok(AvifImage {
-            width: container.width,
-            height: container.height,
+            width: frame.width,
+            height: frame.height,
             pixels,
-            bit_depth: seq_header.bit_depth,
+            bit_depth: frame.bit_depth,
             has_alpha,
         })
     }
     
-    fn decode_tiles(
-        &self,
-        frame: &mut frame::Frame,
-        tile_data: &[obu::TileData],
-        seq_header: &obu::SequenceHeader,
-        frame_header: &obu::FrameHeader,
-    ) -> Result<(), AvifError> {
-        for tile in tile_data {
-            self.decode_tile(frame, tile, seq_header, frame_header)?;
-        }
-        Ok(())
-    }
-    
-    fn decode_tile(
-        &self,
-        frame: &mut frame::Frame,
-        tile: &obu::TileData,
-        seq_header: &obu::SequenceHeader,
-        frame_header: &obu::FrameHeader,
-    ) -> Result<(), AvifError> {
-        let mut decoder = entropy::ArithmeticDecoder::new(&tile.data)?;
-        
-        // Process superblocks
-        let sb_size = if seq_header.use_128x128_superblock { 128 } else { 64 };
-        
-        let tile_col_start = tile.col as u32 * frame_header.tile_info.tile_width_sb * sb_size;
-        let tile_row_start = tile.row as u32 * frame_header.tile_info.tile_height_sb * sb_size;
-        let tile_col_end = ((tile.col + 1) as u32 * frame_header.tile_info.tile_width_sb * sb_size)
-            .min(frame.width);
-        let tile_row_end = ((tile.row + 1) as u32 * frame_header.tile_info.tile_height_sb * sb_size)
-            .min(frame.height);
-        
-        let mut row = tile_row_start;
-        while row < tile_row_end {
-            let mut col = tile_col_start;
-            while col < tile_col_end {
-                self.decode_superblock(
-                    frame,
-                    &mut decoder,
-                    col,
-                    row,
-                    sb_size,
-                    seq_header,
-                    frame_header,
-                )?;
-                col += sb_size;
-            }
-            row += sb_size;
-        }
-        
-        Ok(())
-    }
-    
-    fn decode_superblock(
-        &self,
-        frame: &mut frame::Frame,
-        decoder: &mut entropy::ArithmeticDecoder,
-        sb_col: u32,
-        sb_row: u32,
-        sb_size: u32,
-        seq_header: &obu::SequenceHeader,
-        frame_header: &obu::FrameHeader,
-    ) -> Result<(), AvifError> {
-        // Decode partition tree
-        self.decode_partition(
-            frame,
-            decoder,
-            sb_col,
-            sb_row,
-            sb_size,
-            sb_size,
-            seq_header,
-            frame_header,
-        )
-    }
-    
-    fn decode_partition(
-        &self,
-        frame: &mut frame::Frame,
-        decoder: &mut entropy::ArithmeticDecoder,
-        col: u32,
-        row: u32,
-        width: u32,
-        height: u32,
-        seq_header: &obu::SequenceHeader,
-        frame_header: &obu::FrameHeader,
-    ) -> Result<(), AvifError> {
-        if col >= frame.width || row >= frame.height {
-            return Ok(());
-        }
-        
-        if width <= 4 && height <= 4 {
-            // Minimum block size, decode block
-            return self.decode_block(frame, decoder, col, row, width, height, seq_header, frame_header);
-        }
-        
-        // Decode partition type
-        let partition = decoder.decode_partition(width, height)?;
-        
-        match partition {
-            entropy::Partition::None => {
-                self.decode_block(frame, decoder, col, row, width, height, seq_header, frame_header)?;
-            }
-            entropy::Partition::Horizontal => {
-                let half = height / 2;
-                self.decode_partition(frame, decoder, col, row, width, half, seq_header, frame_header)?;
-                self.decode_partition(frame, decoder, col, row + half, width, half, seq_header, frame_header)?;
-            }
-            entropy::Partition::Vertical => {
-                let half = width / 2;
-                self.decode_partition(frame, decoder, col, row, half, height, seq_header, frame_header)?;
-                self.decode_partition(frame, decoder, col + half, row, half, height, seq_header, frame_header)?;
-            }
-            entropy::Partition::Split => {
-                let half_w = width / 2;
-                let half_h = height / 2;
-                self.decode_partition(frame, decoder, col, row, half_w, half_h, seq_header, frame_header)?;
-                self.decode_partition(frame, decoder, col + half_w, row, half_w, half_h, seq_header, frame_header)?;
-                self.decode_partition(frame, decoder, col, row + half_h, half_w, half_h, seq_header, frame_header)?;
-                self.decode_partition(frame, decoder, col + half_w, row + half_h, half_w, half_h, seq_header, frame_header)?;
-            }
-        }
-        
-        Ok(())
-    }
-    
-    fn decode_block(
-        &self,
-        frame: &mut frame::Frame,
-        decoder: &mut entropy::ArithmeticDecoder,
-        col: u32,
-        row: u32,
-        width: u32,
-        height: u32,
-        seq_header: &obu::SequenceHeader,
-        frame_header: &obu::FrameHeader,
-    ) -> Result<(), AvifError> {
-        // Decode intra prediction mode
-        let mode = decoder.decode_intra_mode()?;
-        
-        // Get reference pixels for prediction
-        let (top, left, top_left) = frame.get_reference_pixels(col, row, width, height, 0);
-        
-        // Allocate prediction buffer
-        let mut prediction = vec![0i16; (width * height) as usize];
-        
-        // Apply intra prediction
-        predict::intra_predict(
-            mode,
-            &mut prediction,
-            width,
-            height,
-            &top,
-            &left,
-            top_left,
-            seq_header.bit_depth,
-        )?;
-        
-        // Decode and apply residuals
-        let has_residual = decoder.decode_bool(128)?;
-        
-        if has_residual {
-            // Decode transform type and size
-            let tx_size = decoder.decode_tx_size(width, height)?;
-            let tx_type = decoder.decode_tx_type()?;
-            
-            // Decode coefficients
-            let mut coeffs = decoder.decode_coefficients(tx_size, seq_header.bit_depth)?;
-            
-            // Apply inverse transform
-            transform::inverse_transform(&mut coeffs, tx_size, tx_type, &self.simd)?;
-            
-            // Add residuals to prediction
-            for (pred, &res) in prediction.iter_mut().zip(coeffs.iter()) {
-                *pred = (*pred + res).clamp(0, (1 << seq_header.bit_depth) - 1);
-            }
-        }
-        
-        // Write to frame buffer
-        frame.write_block(col, row, width, height, &prediction, 0)?;
-        
-        // Handle chroma if not monochrome
-        if !seq_header.monochrome {
-            let chroma_width = width >> seq_header.subsampling_x;
-            let chroma_height = height >> seq_header.subsampling_y;
-            let chroma_col = col >> seq_header.subsampling_x;
-            let chroma_row = row >> seq_header.subsampling_y;
-            
-            // Check for CfL mode
-            let use_cfl = mode == predict::IntraMode::CflPred;
-            
-            for plane in 1..=2 {
-                let chroma_mode = if use_cfl {
-                    predict::IntraMode::CflPred
-                } else {
-                    decoder.decode_chroma_mode(mode)?
-                };
-                
-                let (top, left, top_left) = frame.get_reference_pixels(
-                    chroma_col, chroma_row, chroma_width, chroma_height, plane,
-                );
-                
-                let mut chroma_pred = vec![0i16; (chroma_width * chroma_height) as usize];
-                
-                if use_cfl {
-                    // Get luma average for CfL
-                    let luma_avg = frame.compute_luma_average(col, row, width, height);
-                    let (alpha_u, alpha_v) = decoder.decode_cfl_alpha()?;
-                    let alpha = if plane == 1 { alpha_u } else { alpha_v };
-                    
-                    predict::cfl_predict(
-                        &mut chroma_pred,
-                        chroma_width,
-                        chroma_height,
-                        &top,
-                        &left,
-                        top_left,
-                        alpha,
-                        luma_avg,
-                        seq_header.bit_depth,
-                    )?;
-                } else {
-                    predict::intra_predict(
-                        chroma_mode,
-                        &mut chroma_pred,
-                        chroma_width,
-                        chroma_height,
-                        &top,
-                        &left,
-                        top_left,
-                        seq_header.bit_depth,
-                    )?;
-                }
-                
-                let has_chroma_residual = decoder.decode_bool(128)?;
-                
-                if has_chroma_residual {
-                    let tx_size = decoder.decode_tx_size(chroma_width, chroma_height)?;
-                    let tx_type = decoder.decode_tx_type()?;
-                    let mut coeffs = decoder.decode_coefficients(tx_size, seq_header.bit_depth)?;
-                    
-                    transform::inverse_transform(&mut coeffs, tx_size, tx_type, &self.simd)?;
-                    
-                    for (pred, &res) in chroma_pred.iter_mut().zip(coeffs.iter()) {
-                        *pred = (*pred + res).clamp(0, (1 << seq_header.bit_depth) - 1);
-                    }
-                }
-                
-                frame.write_block(chroma_col, chroma_row, chroma_width, chroma_height, &chroma_pred, plane)?;
-            }
-        }
-        
-        Ok(())
-    }
-    
     fn decode_alpha(
-        &mut self,
+        &self,
         data: &[u8],
         alpha_data: &container::AlphaData,
     ) -> Result<Vec<u8>, AvifError> {
         // Alpha is stored as a separate AV1 monochrome image
-        let av1_data = &data[alpha_data.offset..alpha_data.offset + alpha_data.size];
-        let (seq_header, frame_header, tile_data) = obu::parse_obus(av1_data)?;
-        
-        let mut frame = frame::Frame::new(
-            alpha_data.width,
-            alpha_data.height,
-            seq_header.bit_depth,
-            0, 0,
-            true, // monochrome
-        );
-        
-        self.decode_tiles(&mut frame, &tile_data, &seq_header, &frame_header)?;
+        let av1_data = data.get(alpha_data.offset..alpha_data.offset + alpha_data.size)
+            .ok_or(AvifError::InvalidData)?;
+        let frame = decode_av1(av1_data)?;
         
         // Extract alpha values
         let alpha: Vec<u8> = frame.planes[0].data.iter()
             .map(|&v| {
-                if seq_header.bit_depth == 8 {
+                if frame.bit_depth == 8 {
                     v as u8
                 } else {
-                    (v >> (seq_header.bit_depth - 8)) as u8
+                    (v >> (frame.bit_depth - 8)) as u8
                 }
             })
             .collect();
@@ -435,21 +143,33 @@ impl AvifDecoder {
         Ok(alpha)
     }
     
-    fn combine_alpha(&self, rgb: Vec<u8>, alpha: &[u8]) -> Vec<u8> {
-        let mut rgba = Vec::with_capacity((rgb.len() / 3) * 4);
-        let pixels = rgb.len() / 3;
-        
-        for i in 0..pixels {
-            rgba.push(rgb[i * 3]);
-            rgba.push(rgb[i * 3 + 1]);
-            rgba.push(rgb[i * 3 + 2]);
-            rgba.push(alpha.get(i).copied().unwrap_or(255));
+    fn combine_alpha(&self, mut rgba: Vec<u8>, alpha: &[u8]) -> Vec<u8> {
+        for (pixel, &a) in rgba.chunks_exact_mut(4).zip(alpha) {
+            pixel[3] = a;
         }
-        
         rgba
     }
 }
 
+/// Decode the key frame of an image item's AV1 data
+fn decode_av1(data: &[u8]) -> Result<frame::Frame, AvifError> {
+    let mut decoder = fos_av1::Av1Decoder::new();
+    let picture = decoder.decode(data)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AvifError::DecodingError("No frame in AV1 data".into()))?;
+    Ok(frame::Frame::from_picture(&picture))
+}
+
+impl From<fos_av1::Av1Error> for AvifError {
+    fn from(e: fos_av1::Av1Error) -> Self {
+        match e {
+            fos_av1::Av1Error::Unsupported(msg) => Self::DecodingError(format!("Unsupported: {}", msg)),
+            e => Self::DecodingError(e.to_string()),
+        }
+    }
+}
+
 /// Check if data is AVIF format
 pub fn is_avif(data: &[u8]) -> bool {
     if data.len() < 12 {
@@ -484,6 +204,25 @@ mod tests {
         assert!(!is_avif(too_short));
     }
     
+    #[test]
+    fn test_decode_av1_key_frame() {
+        let frame = decode_av1(fos_av1::fixtures::TEST_STREAM[0]).unwrap();
+        assert_eq!((frame.width, frame.height, frame.subsampling_x, frame.subsampling_y), (16, 16, 1, 1));
+        assert_eq!(frame.planes.iter().map(|p| (p.width, p.height)).collect::<Vec<_>>(), [(16, 16), (8, 8), (8, 8)]);
+        let sum: i32 = frame.planes[0].data.iter().map(|&v| v as i32).sum();
+        assert_eq!(sum, 19402);
+        
+        let color = ColorInfo { primaries: 1, transfer: 13, matrix: 1 };
+        let rgba = color::yuv_to_rgba(&frame, &color, &SimdOps::new()).unwrap();
+        assert_eq!(rgba.len(), 16 * 16 * 4);
+    }
+    
+    #[test]
+    fn test_truncated_av1_data_is_an_error() {
+        let data = &fos_av1::fixtures::TEST_STREAM[0][..20];
+        assert!(matches!(decode_av1(data), Err(AvifError::DecodingError(_))));
+    }
+    
     #[test]
     fn test_decoder_creation() {
         let decoder = AvifDecoder::new();
@@ -51,6 +51,8 @@ pub struct ResponsiveImageResolver {
     srcset: Vec<SrcsetEntry>,
     /// Parsed sizes entries
     sizes: Vec<SizesEntry>,
+    /// Data saver is on: pick candidates for 1x screens
+    save_data: bool,
 }
 
 impl ResponsiveImageResolver {
@@ -59,11 +61,21 @@ impl ResponsiveImageResolver {
         Self {
             srcset: parse_srcset(srcset),
             sizes: sizes.map(parse_sizes).unwrap_or_default(),
+            save_data: false,
         }
     }
     
+    /// Follow the data saver preference, trading sharpness on high density
+    /// screens for smaller downloads
+    pub fn with_save_data(mut self, enabled: bool) -> Self {
+        self.save_data = enabled;
+        self
+    }
+    
     /// Select best image for given viewport and device pixel ratio
     pub fn select(&self, viewport_width: u32, device_pixel_ratio: f32) -> Option<&SrcsetEntry> {
+        let device_pixel_ratio = if self.save_data { device_pixel_ratio.min(1.0) } else { device_pixel_ratio };
+        
         // Calculate effective slot width
         let slot_width = self.calculate_slot_width(viewport_width);
         let target_width = (slot_width * device_pixel_ratio) as u32;
@@ -292,4 +304,16 @@ mod tests {
         let selected = resolver.select(1200, 1.0).unwrap();
         assert_eq!(selected.url, "medium.jpg"); // 600px slot (50vw), need 600w
     }
+    
+    #[test]
+    fn test_save_data_selection() {
+        let srcset = "small.jpg 300w, medium.jpg 600w, large.jpg 1200w";
+        let resolver = ResponsiveImageResolver::new(srcset, None);
+        assert_eq!(resolver.select(500, 2.0).unwrap().url, "large.jpg");
+        
+        let resolver = ResponsiveImageResolver::new(srcset, None).with_save_data(true);
+        assert_eq!(resolver.select(500, 2.0).unwrap().url, "medium.jpg");
+        let resolver = ResponsiveImageResolver::new("a.jpg, a@2x.jpg 2x", None).with_save_data(true);
+        assert_eq!(resolver.select(500, 3.0).unwrap().url, "a.jpg");
+    }
 }
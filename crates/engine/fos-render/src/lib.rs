@@ -14,7 +14,10 @@
 //! - CSS transforms (rotate, scale, skew, translate)
 //! - CSS animations (transitions, keyframes)
 //! - CSS filters (blur, brightness, contrast, etc.)
+//! - DevTools highlight overlay of box-model regions
+//! - Native form controls themed by color-scheme and accent-color
 //! - GPU compositing with tiered memory management
+//! - GL device executing WebGL draw calls with GLSL ES translation
 //! - Tile-based rendering with pooled buffers
 //! - Copy-on-Write layer management
 
@@ -30,6 +33,8 @@ pub mod effects;
 pub mod transform;
 pub mod animation;
 pub mod filters;
+pub mod overlay;
+pub mod form_controls;
 pub mod compositor;
 pub mod layers;
 pub mod gpu;
@@ -63,11 +68,13 @@ pub use animation::{
     TimingFunction, Transition, Keyframe, KeyframeAnimation, 
     AnimatedValue, AnimationInstance, AnimationDirection, FillMode
 };
+pub use overlay::HighlightOverlay;
+pub use form_controls::{FormControlKind, FormControlState, FormControlTheme, paint_form_control};
 pub use filters::{
     FilterFunction, FilterList, BlendMode, apply_filters,
     brightness_4, grayscale_4, blend_4, invert_4, alpha_blend_4,
 };
-pub use gpu::{GpuRenderer, GpuConfig, GpuState, GpuBackend, RenderFrame, TextureId, GpuError};
+pub use gpu::{GpuRenderer, GpuConfig, GpuState, GpuBackend, RenderFrame, TextureId, GpuError, GlDevice};
 pub use webgl::{WebGLRenderingContext, WebGLVersion, ShaderType, TextureFormat};
 pub use webgpu::{GPUDevice, GPURenderPipeline, GPUComputePipeline, GPUBuffer, GPUTexture};
 pub use render_opt::{DisplayList, TextureAtlas, DirtyRectTracker, OcclusionCuller, RenderTreeDiffer, OffscreenCanvas};
@@ -0,0 +1,88 @@
+//! Inspector highlight overlay
+//!
+//! Paints the box-model regions of the node hovered in DevTools on top of
+//! the page: content, padding, border and margin each get their own
+//! translucent tint. Regions are painted as non-overlapping rings so the
+//! tints never stack.
+
+use crate::{Canvas, Color};
+use fos_layout::{BoxDimensions, Rect};
+
+/// Colors of the highlighted box-model regions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HighlightOverlay {
+    pub content: Color,
+    pub padding: Color,
+    pub border: Color,
+    pub margin: Color,
+}
+
+impl Default for HighlightOverlay {
+    fn default() -> Self {
+        Self {
+            content: Color::rgba(111, 168, 220, 168),
+            padding: Color::rgba(147, 196, 125, 140),
+            border: Color::rgba(255, 229, 153, 168),
+            margin: Color::rgba(246, 178, 107, 168),
+        }
+    }
+}
+
+impl HighlightOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Paint the regions of a laid out box
+    pub fn paint(&self, canvas: &mut Canvas, dims: &BoxDimensions) {
+        let content = dims.content_box();
+        let padding = dims.padding_box();
+        let border = dims.border_box();
+        let margin = dims.margin_box();
+        
+        fill_ring(canvas, &margin, &border, self.margin);
+        fill_ring(canvas, &border, &padding, self.border);
+        fill_ring(canvas, &padding, &content, self.padding);
+        canvas.fill_rect(content.x, content.y, content.width, content.height, self.content);
+    }
+}
+
+/// Fill the area of `outer` that is not covered by `inner`
+fn fill_ring(canvas: &mut Canvas, outer: &Rect, inner: &Rect, color: Color) {
+    // Top and bottom span the full width, left and right fill in between
+    canvas.fill_rect(outer.x, outer.y, outer.width, inner.y - outer.y, color);
+    canvas.fill_rect(outer.x, inner.bottom(), outer.width, outer.bottom() - inner.bottom(), color);
+    canvas.fill_rect(outer.x, inner.y, inner.x - outer.x, inner.height, color);
+    canvas.fill_rect(inner.right(), inner.y, outer.right() - inner.right(), inner.height, color);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fos_layout::EdgeSizes;
+    
+    #[test]
+    fn test_highlight_regions() {
+        let mut canvas = Canvas::new(40, 40).unwrap();
+        let edges = |v| EdgeSizes { top: v, right: v, bottom: v, left: v };
+        let dims = BoxDimensions {
+            content: Rect::new(10.0, 10.0, 20.0, 20.0),
+            padding: edges(2.0),
+            border: edges(2.0),
+            margin: edges(2.0),
+        };
+        let overlay = HighlightOverlay {
+            content: Color::RED,
+            padding: Color::GREEN,
+            border: Color::BLUE,
+            margin: Color::WHITE,
+        };
+        overlay.paint(&mut canvas, &dims);
+        
+        assert_eq!(canvas.get_pixel(20, 20), Some(Color::RED));
+        assert_eq!(canvas.get_pixel(9, 20), Some(Color::GREEN));
+        assert_eq!(canvas.get_pixel(20, 7), Some(Color::BLUE));
+        assert_eq!(canvas.get_pixel(5, 5), Some(Color::WHITE));
+        assert_eq!(canvas.get_pixel(3, 3), Some(Color::TRANSPARENT));
+    }
+}
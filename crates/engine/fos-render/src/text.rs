@@ -1,13 +1,18 @@
 //! Text rendering module
 //!
 //! Integrates fos-text for rendering text content on the canvas.
+//! Color emoji (COLR layers, CBDT/sbix bitmaps) are rasterized to RGBA and
+//! cached in the glyph atlas like outline glyphs.
 
 use crate::{Canvas, Color};
+use crate::image::ImageDecoder;
 use fos_text::{
     FontDatabase, FontId, FontQuery, 
     TextShaper,
-    GlyphRasterizer, GlyphAtlas, GlyphKey,
+    GlyphRasterizer, GlyphAtlas, GlyphKey, RasterizedGlyph,
 };
+use fos_text::font::{ColorFont, ColorGlyph, EmojiRenderer, FontParser, GlyphId, PaletteColor, VariableFontInstance};
+use fos_text::font::emoji::ColorBitmap;
 
 /// Text renderer that integrates with the canvas
 pub struct TextRenderer {
@@ -59,6 +64,27 @@ impl TextRenderer {
         font_size: f32,
         color: Color,
     ) {
+        self.draw_text_with_variations(canvas, text, x, y, font_id, font_size, color, &VariableFontInstance::new());
+    }
+    
+    /// Render text with a variable font instance, e.g. from
+    /// `font-variation-settings` or a running axis transition
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text_with_variations(
+        &mut self,
+        canvas: &mut Canvas,
+        text: &str,
+        x: f32,
+        y: f32,
+        font_id: FontId,
+        font_size: f32,
+        color: Color,
+        instance: &VariableFontInstance,
+    ) {
+        // Glyphs are rasterized at device size
+        let ratio = canvas.scale();
+        let (x, y, font_size) = (x * ratio, y * ratio, font_size * ratio);
+        
         // Shape the text
         let shaped = match self.shaper.shape(&self.fonts, font_id, text, font_size) {
             Ok(s) => s,
@@ -82,13 +108,35 @@ impl TextRenderer {
         font_id.0.hash(&mut hasher);
         let font_idx = hasher.finish() as u32;
         
+        let parser = match FontParser::parse(&font_data) {
+            Ok(p) => p,
+            Err(_) => return,
+        };
+        let coords = parser.normalized_coords(instance);
+        
+        // Color glyphs take the first palette; foreground layers use the
+        // text color, so it is part of their cache key
+        let color_font = ColorFont::new(&parser);
+        let mut emoji = color_font.has_color().then(|| {
+            let mut emoji = EmojiRenderer::new();
+            if let Some(palette) = color_font.palette(0) {
+                emoji.set_palette(palette);
+            }
+            emoji
+        });
+        
         for glyph in &shaped.glyphs {
-            let key = GlyphKey::new(font_idx, glyph.glyph_id, font_size);
+            let mut key = GlyphKey::new(font_idx, glyph.glyph_id, font_size).with_coords(&coords);
+            if emoji.is_some() {
+                key = key.with_foreground(color.r, color.g, color.b, color.a);
+            }
             
             // Get or rasterize glyph, clone to avoid borrow conflict
             let rasterized = self.atlas.get_or_insert_with(key, || {
-                self.rasterizer.rasterize(&font_data, 0, glyph.glyph_id, font_size)
-                    .unwrap_or_else(|| fos_text::RasterizedGlyph::empty(glyph.glyph_id))
+                emoji.as_mut()
+                    .and_then(|emoji| rasterize_color_glyph(&self.rasterizer, &parser, &color_font, emoji, glyph.glyph_id, font_size, color))
+                    .or_else(|| self.rasterizer.rasterize_with_coords(&parser, glyph.glyph_id, font_size, &coords))
+                    .unwrap_or_else(|| RasterizedGlyph::empty(glyph.glyph_id))
             }).clone();
             
             if rasterized.width > 0 && rasterized.height > 0 {
@@ -96,18 +144,28 @@ impl TextRenderer {
                 let gx = cursor_x + glyph.x_offset as f32 * scale + rasterized.bearing_x as f32;
                 let gy = y + glyph.y_offset as f32 * scale - rasterized.bearing_y as f32;
                 
-                draw_glyph_bitmap(
-                    canvas,
-                    &rasterized.bitmap,
-                    rasterized.width,
-                    rasterized.height,
-                    gx,
-                    gy,
-                    color,
-                );
+                if rasterized.color {
+                    draw_color_bitmap(canvas, &rasterized.bitmap, rasterized.width, rasterized.height, gx, gy);
+                } else {
+                    draw_glyph_bitmap(
+                        canvas,
+                        &rasterized.bitmap,
+                        rasterized.width,
+                        rasterized.height,
+                        gx,
+                        gy,
+                        color,
+                    );
+                }
             }
             
-            cursor_x += glyph.x_advance as f32 * scale;
+            // Shaping used default advances; add the instance's delta
+            let gid = GlyphId(glyph.glyph_id);
+            let advance_delta = match (parser.glyph_hor_advance(gid), parser.glyph_hor_advance_with_coords(gid, &coords)) {
+                (Some(default), Some(varied)) => varied - default as f32,
+                _ => 0.0,
+            };
+            cursor_x += (glyph.x_advance as f32 + advance_delta) * scale;
         }
     }
     
@@ -156,6 +214,73 @@ fn draw_glyph_bitmap(
     }
 }
 
+/// Draw a straight-alpha RGBA glyph bitmap onto the canvas
+fn draw_color_bitmap(canvas: &mut Canvas, bitmap: &[u8], width: u32, height: u32, x: f32, y: f32) {
+    let ix = x as i32;
+    let iy = y as i32;
+    
+    for (i, pixel) in bitmap.chunks_exact(4).take((width * height) as usize).enumerate() {
+        let [r, g, b, alpha] = [pixel[0], pixel[1], pixel[2], pixel[3]];
+        let canvas_x = ix + (i as u32 % width) as i32;
+        let canvas_y = iy + (i as u32 / width) as i32;
+        if alpha == 0 || canvas_x < 0 || canvas_y < 0 {
+            continue;
+        }
+        if let Some(existing) = canvas.get_pixel(canvas_x as u32, canvas_y as u32) {
+            let blended = blend_pixel(existing, Color::rgba(r, g, b, 255), alpha);
+            canvas.set_pixel(canvas_x as u32, canvas_y as u32, blended);
+        }
+    }
+}
+
+/// Rasterize a color glyph; `None` if the glyph has no color version or
+/// only an SVG one, which falls back to the outline
+fn rasterize_color_glyph(
+    rasterizer: &GlyphRasterizer,
+    parser: &FontParser,
+    font: &ColorFont,
+    emoji: &mut EmojiRenderer,
+    glyph_id: u16,
+    font_size: f32,
+    color: Color,
+) -> Option<RasterizedGlyph> {
+    let ppem = font_size.round().max(1.0) as u16;
+    match emoji.load_glyph(font, glyph_id, ppem)?.clone() {
+        ColorGlyph::Layered(layers) => {
+            let foreground = PaletteColor::new(color.r, color.g, color.b, color.a);
+            let layers = emoji.layer_colors(&layers, foreground);
+            rasterizer.rasterize_layers(parser, glyph_id, &layers, font_size)
+        }
+        ColorGlyph::Bitmap(bitmap) => scale_bitmap_glyph(glyph_id, &bitmap, font_size),
+        ColorGlyph::Svg(_) => None,
+    }
+}
+
+/// Decode a CBDT/sbix strike bitmap and scale it from the strike size to
+/// the font size
+fn scale_bitmap_glyph(glyph_id: u16, bitmap: &ColorBitmap, font_size: f32) -> Option<RasterizedGlyph> {
+    let (width, height) = if bitmap.width > 0 && bitmap.height > 0 {
+        (bitmap.width as u32, bitmap.height as u32)
+    } else {
+        let image = ImageDecoder::decode(&bitmap.data).ok()?;
+        (image.width, image.height)
+    };
+    let scale = font_size / bitmap.ppem.max(1) as f32;
+    let target_width = ((width as f32 * scale).round() as u32).max(1);
+    let target_height = ((height as f32 * scale).round() as u32).max(1);
+    let image = ImageDecoder::decode_resized(&bitmap.data, target_width, target_height).ok()?;
+    
+    Some(RasterizedGlyph {
+        glyph_id,
+        width: image.width,
+        height: image.height,
+        bearing_x: (bitmap.bearing_x as f32 * scale).round() as i32,
+        bearing_y: (bitmap.bearing_y as f32 * scale).round() as i32,
+        bitmap: image.pixels,
+        color: true,
+    })
+}
+
 impl Default for TextRenderer {
     fn default() -> Self {
         Self::new()
@@ -194,4 +319,18 @@ mod tests {
         // Should be roughly gray
         assert!(result.r > 100 && result.r < 160);
     }
+    
+    #[test]
+    fn test_draw_color_bitmap() {
+        let mut canvas = Canvas::new(4, 4).unwrap();
+        canvas.clear(Color::WHITE);
+        // Opaque red and half-transparent blue pixels
+        let bitmap = [255, 0, 0, 255, 0, 0, 255, 128];
+        draw_color_bitmap(&mut canvas, &bitmap, 2, 1, 1.0, 2.0);
+        let red = canvas.get_pixel(1, 2).unwrap();
+        assert_eq!((red.r, red.g, red.b), (255, 0, 0));
+        let blue = canvas.get_pixel(2, 2).unwrap();
+        assert!(blue.b == 255 && blue.r > 100 && blue.r < 160);
+        assert_eq!(canvas.get_pixel(3, 2).unwrap().r, 255);
+    }
 }
@@ -1,7 +1,8 @@
 //! Custom Brotli Decompressor
 //!
 //! From-scratch implementation of Brotli decompression (RFC 7932)
-//! optimized for WOFF2 font decoding with shared dictionary support.
+//! for WOFF2 font decoding, including the built-in static dictionary,
+//! its word transforms and optional custom (shared) dictionaries.
 
 
 /// Brotli decompression error
@@ -35,15 +36,17 @@ impl std::error::Error for BrotliError {}
 /// Result type for Brotli operations
 pub type BrotliResult<T> = Result<T, BrotliError>;
 
+/// Default cap on decompressed output (guards against decompression bombs)
+const DEFAULT_OUTPUT_LIMIT: usize = 256 * 1024 * 1024;
+
 // ============================================================================
 // Bit Reader
 // ============================================================================
 
-/// Bit-level reader for Brotli stream
+/// Bit-level reader for Brotli stream (least significant bit first)
 struct BitReader<'a> {
     data: &'a [u8],
     pos: usize,
-    bit_pos: u8,
     /// Bit accumulator for faster reading
     accumulator: u64,
     /// Bits available in accumulator
@@ -55,7 +58,6 @@ impl<'a> BitReader<'a> {
         let mut reader = Self {
             data,
             pos: 0,
-            bit_pos: 0,
             accumulator: 0,
             bits_available: 0,
         };
@@ -79,21 +81,16 @@ impl<'a> BitReader<'a> {
         if n == 0 {
             return Ok(0);
         }
-        if n > 32 {
-            return Err(BrotliError::InvalidStream);
-        }
-
-        self.refill();
-
         if self.bits_available < n {
-            return Err(BrotliError::UnexpectedEof);
+            self.refill();
+            if self.bits_available < n {
+                return Err(BrotliError::UnexpectedEof);
+            }
         }
-
-        let mask = (1u64 << n) - 1;
-        let result = (self.accumulator & mask) as u32;
+        let value = (self.accumulator & ((1u64 << n) - 1)) as u32;
         self.accumulator >>= n;
         self.bits_available -= n;
-        Ok(result)
+        Ok(value)
     }
 
     /// Read a single bit
@@ -102,501 +99,412 @@ impl<'a> BitReader<'a> {
         Ok(self.read_bits(1)? != 0)
     }
 
-    /// Peek bits without consuming
+    /// Peek up to 32 bits without consuming them.
+    ///
+    /// Returns the bits (zero-padded past the end of input) and how many
+    /// of them are actually backed by input.
     #[inline]
-    fn peek_bits(&mut self, n: u8) -> BrotliResult<u32> {
-        self.refill();
+    fn peek_bits(&mut self, n: u8) -> (u32, u8) {
         if self.bits_available < n {
-            return Err(BrotliError::UnexpectedEof);
+            self.refill();
         }
-        let mask = (1u64 << n) - 1;
-        Ok((self.accumulator & mask) as u32)
+        let value = (self.accumulator & ((1u64 << n) - 1)) as u32;
+        (value, self.bits_available.min(n))
     }
 
-    /// Drop bits after peeking
+    /// Drop bits previously inspected with `peek_bits`
     #[inline]
-    fn drop_bits(&mut self, n: u8) {
+    fn consume(&mut self, n: u8) {
         self.accumulator >>= n;
-        self.bits_available = self.bits_available.saturating_sub(n);
+        self.bits_available -= n;
+    }
+
+    /// Skip to the next byte boundary; the padding bits must be zero
+    fn align_to_byte(&mut self) -> BrotliResult<()> {
+        let padding = self.bits_available % 8;
+        if self.read_bits(padding)? != 0 {
+            return Err(BrotliError::InvalidStream);
+        }
+        Ok(())
+    }
+
+    /// Read a whole byte (only meaningful when aligned)
+    #[inline]
+    fn read_byte(&mut self) -> BrotliResult<u8> {
+        Ok(self.read_bits(8)? as u8)
     }
+}
 
-    /// Check if at end
-    fn is_empty(&self) -> bool {
-        self.bits_available == 0 && self.pos >= self.data.len()
+/// Read a VarLenUint8 value (0..=255)
+fn read_varlen_u8(reader: &mut BitReader) -> BrotliResult<u32> {
+    if !reader.read_bit()? {
+        return Ok(0);
+    }
+    let n = reader.read_bits(3)? as u8;
+    if n == 0 {
+        return Ok(1);
     }
+    Ok((1 << n) + reader.read_bits(n)?)
 }
 
 // ============================================================================
 // Huffman Decoding
 // ============================================================================
 
-/// Maximum Huffman code length
-const MAX_HUFFMAN_BITS: usize = 15;
-/// Lookup table size for fast decoding
-const HUFFMAN_TABLE_BITS: usize = 8;
-const HUFFMAN_TABLE_SIZE: usize = 1 << HUFFMAN_TABLE_BITS;
+/// Longest prefix code allowed by the format
+const MAX_CODE_LENGTH: usize = 15;
 
-/// Huffman decoder with fast lookup table
+/// Number of bits resolved by the direct lookup table
+const FAST_BITS: u8 = 8;
+
+/// Canonical prefix code decoder
 struct HuffmanDecoder {
-    /// Fast lookup for short codes: (symbol, bits)
-    table: Vec<(u16, u8)>,
-    /// Symbol count
-    num_symbols: u16,
-    /// Maximum code length
-    max_bits: u8,
+    /// Number of codes of each length
+    counts: [u16; MAX_CODE_LENGTH + 1],
+    /// Symbols ordered by (code length, symbol value)
+    symbols: Vec<u16>,
+    /// Direct lookup for short codes: `symbol << 4 | length`, 0 if none
+    fast: Vec<u16>,
+    /// Set when the code has a single symbol, which consumes no bits
+    single: Option<u16>,
 }
 
 impl HuffmanDecoder {
-    /// Build from code lengths (0 = not used)
-    fn from_lengths(code_lengths: &[u8]) -> BrotliResult<Self> {
-        let num_symbols = code_lengths.len() as u16;
-        
-        if num_symbols == 0 {
-            return Ok(Self {
-                table: vec![(0, 0); HUFFMAN_TABLE_SIZE],
-                num_symbols: 0,
-                max_bits: 0,
-            });
-        }
-
-        // Count codes of each length
-        let mut bl_count = [0u32; MAX_HUFFMAN_BITS + 1];
-        let mut max_bits = 0u8;
-        
-        for &len in code_lengths {
+    /// Build a decoder from per-symbol code lengths
+    fn from_lengths(lengths: &[u8]) -> BrotliResult<Self> {
+        let mut counts = [0u16; MAX_CODE_LENGTH + 1];
+        let mut used = 0usize;
+        let mut last_symbol = 0u16;
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len as usize > MAX_CODE_LENGTH {
+                return Err(BrotliError::InvalidHuffman);
+            }
             if len > 0 {
-                bl_count[len as usize] += 1;
-                max_bits = max_bits.max(len);
+                counts[len as usize] += 1;
+                used += 1;
+                last_symbol = symbol as u16;
             }
         }
 
-        // Handle single-symbol case
-        let non_zero: usize = code_lengths.iter().filter(|&&l| l > 0).count();
-        if non_zero == 0 {
-            return Ok(Self {
-                table: vec![(0, 0); HUFFMAN_TABLE_SIZE],
-                num_symbols,
-                max_bits: 0,
-            });
+        if used == 0 {
+            return Err(BrotliError::InvalidHuffman);
         }
-        if non_zero == 1 {
-            let symbol = code_lengths.iter().position(|&l| l > 0).unwrap() as u16;
+        if used == 1 {
             return Ok(Self {
-                table: vec![(symbol, 0); HUFFMAN_TABLE_SIZE],
-                num_symbols,
-                max_bits: 0,
+                counts,
+                symbols: vec![last_symbol],
+                fast: Vec::new(),
+                single: Some(last_symbol),
             });
         }
 
-        // Calculate first code for each length
-        let mut next_code = [0u32; MAX_HUFFMAN_BITS + 1];
-        let mut code = 0u32;
-        for bits in 1..=max_bits as usize {
-            code = (code + bl_count[bits - 1]) << 1;
-            next_code[bits] = code;
+        // The code must be complete: neither over-subscribed nor leaving gaps
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(BrotliError::InvalidHuffman);
+            }
+        }
+        if left != 0 {
+            return Err(BrotliError::InvalidHuffman);
         }
 
-        // Assign codes to symbols
-        let mut symbols = Vec::with_capacity(num_symbols as usize);
-        for (sym, &len) in code_lengths.iter().enumerate() {
+        let mut offsets = [0usize; MAX_CODE_LENGTH + 2];
+        for len in 1..=MAX_CODE_LENGTH {
+            offsets[len + 1] = offsets[len] + counts[len] as usize;
+        }
+        let mut symbols = vec![0u16; used];
+        for (symbol, &len) in lengths.iter().enumerate() {
             if len > 0 {
-                symbols.push((sym as u16, next_code[len as usize], len));
-                next_code[len as usize] += 1;
+                symbols[offsets[len as usize]] = symbol as u16;
+                offsets[len as usize] += 1;
             }
         }
 
-        // Build lookup table
-        let mut table = vec![(0u16, 0u8); HUFFMAN_TABLE_SIZE];
-        
-        for &(symbol, code, bits) in &symbols {
-            if bits as usize <= HUFFMAN_TABLE_BITS {
-                // Fill table entries for this symbol
-                let base_idx = Self::reverse_bits(code, bits) as usize;
-                let step = 1 << bits;
-                let mut idx = base_idx;
-                while idx < HUFFMAN_TABLE_SIZE {
-                    table[idx] = (symbol, bits);
-                    idx += step;
-                }
-            } else {
-                // For longer codes, store with special marker
-                // We use secondary table approach for these
-                let prefix = Self::reverse_bits(code, bits) as usize & (HUFFMAN_TABLE_SIZE - 1);
-                if table[prefix].1 == 0 {
-                    table[prefix] = (symbol, bits);
+        // Fill the direct lookup table; stream bits arrive reversed
+        let mut fast = vec![0u16; 1 << FAST_BITS];
+        let mut code = 0u32;
+        let mut index = 0usize;
+        for (len, &count) in counts.iter().enumerate().take(FAST_BITS as usize + 1).skip(1) {
+            for _ in 0..count {
+                let reversed = code.reverse_bits() >> (32 - len);
+                let entry = (symbols[index] << 4) | len as u16;
+                let mut slot = reversed as usize;
+                while slot < fast.len() {
+                    fast[slot] = entry;
+                    slot += 1 << len;
                 }
+                code += 1;
+                index += 1;
             }
+            code <<= 1;
         }
 
         Ok(Self {
-            table,
-            num_symbols,
-            max_bits,
+            counts,
+            symbols,
+            fast,
+            single: None,
         })
     }
 
-    /// Reverse bits of a code
-    #[inline]
-    fn reverse_bits(mut code: u32, bits: u8) -> u32 {
-        let mut result = 0u32;
-        for _ in 0..bits {
-            result = (result << 1) | (code & 1);
-            code >>= 1;
-        }
-        result
-    }
-
     /// Decode one symbol
+    #[inline]
     fn decode(&self, reader: &mut BitReader) -> BrotliResult<u16> {
-        if self.max_bits == 0 {
-            // Single symbol or empty
-            return Ok(self.table[0].0);
+        if let Some(symbol) = self.single {
+            return Ok(symbol);
         }
 
-        let peek = reader.peek_bits((HUFFMAN_TABLE_BITS as u8).min(self.max_bits))? as usize;
-        let (symbol, bits) = self.table[peek & (HUFFMAN_TABLE_SIZE - 1)];
-        
-        if bits <= HUFFMAN_TABLE_BITS as u8 {
-            reader.drop_bits(bits);
-            Ok(symbol)
-        } else {
-            // Slow path for long codes
-            self.decode_slow(reader)
+        let (bits, available) = reader.peek_bits(FAST_BITS);
+        let entry = self.fast[bits as usize];
+        if entry != 0 {
+            let len = (entry & 0xF) as u8;
+            if len > available {
+                return Err(BrotliError::UnexpectedEof);
+            }
+            reader.consume(len);
+            return Ok(entry >> 4);
         }
-    }
 
-    fn decode_slow(&self, reader: &mut BitReader) -> BrotliResult<u16> {
-        // Read bit by bit (fallback for long codes)
-        let mut code = 0u32;
-        for bits in 1..=self.max_bits {
-            code = (code << 1) | reader.read_bits(1)?;
-            // Linear search (rare case)
-            for i in 0..self.num_symbols as usize {
-                if self.table[i % HUFFMAN_TABLE_SIZE] == (i as u16, bits) {
-                    return Ok(i as u16);
-                }
+        // Slow path: walk the canonical code one bit at a time
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for len in 1..=MAX_CODE_LENGTH {
+            code |= reader.read_bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
             }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
         }
         Err(BrotliError::InvalidHuffman)
     }
 }
 
-// ============================================================================
-// Ring Buffer for LZ77
-// ============================================================================
-
-/// Ring buffer for back-references
-struct RingBuffer {
-    buffer: Vec<u8>,
-    pos: usize,
-    size: usize,
-}
-
-impl RingBuffer {
-    fn new(window_bits: u8) -> Self {
-        let size = 1 << window_bits;
-        Self {
-            buffer: vec![0; size],
-            pos: 0,
-            size,
-        }
-    }
-
-    #[inline]
-    fn push(&mut self, byte: u8) {
-        self.buffer[self.pos] = byte;
-        self.pos = (self.pos + 1) & (self.size - 1);
-    }
-
-    #[inline]
-    fn get(&self, distance: usize) -> u8 {
-        let idx = (self.pos.wrapping_sub(distance)) & (self.size - 1);
-        self.buffer[idx]
-    }
-
-    fn copy_match(&mut self, distance: usize, length: usize, output: &mut Vec<u8>) {
-        for _ in 0..length {
-            let byte = self.get(distance);
-            output.push(byte);
-            self.push(byte);
-        }
+/// Number of bits needed to encode a symbol of the given alphabet
+fn alphabet_bits(alphabet_size: usize) -> u8 {
+    let mut bits = 0;
+    while (1usize << bits) < alphabet_size {
+        bits += 1;
     }
+    bits
 }
 
-// ============================================================================
-// Prefix Codes
-// ============================================================================
-
 /// Read simple prefix code (Brotli's simplified Huffman format)
-fn read_simple_prefix_code(reader: &mut BitReader, alphabet_size: u16) -> BrotliResult<HuffmanDecoder> {
-    let num_symbols = reader.read_bits(2)? + 1;
-    
-    let bits_needed = if alphabet_size > 1 {
-        (16 - (alphabet_size - 1).leading_zeros()) as u8
-    } else {
-        0
-    };
-
-    let mut symbols = Vec::with_capacity(num_symbols as usize);
-    for _ in 0..num_symbols {
-        let sym = reader.read_bits(bits_needed)? as u16;
-        if sym >= alphabet_size {
+fn read_simple_prefix_code(reader: &mut BitReader, alphabet_size: usize) -> BrotliResult<HuffmanDecoder> {
+    let num_symbols = reader.read_bits(2)? as usize + 1;
+    let bits = alphabet_bits(alphabet_size);
+
+    let mut symbols = [0usize; 4];
+    for i in 0..num_symbols {
+        let symbol = reader.read_bits(bits)? as usize;
+        if symbol >= alphabet_size || symbols[..i].contains(&symbol) {
             return Err(BrotliError::InvalidHuffman);
         }
-        symbols.push(sym);
+        symbols[i] = symbol;
     }
 
-    // Build code lengths based on number of symbols
-    let mut code_lengths = vec![0u8; alphabet_size as usize];
-    match num_symbols {
-        1 => {
-            code_lengths[symbols[0] as usize] = 1;
-        }
-        2 => {
-            code_lengths[symbols[0] as usize] = 1;
-            code_lengths[symbols[1] as usize] = 1;
-        }
-        3 => {
-            code_lengths[symbols[0] as usize] = 1;
-            code_lengths[symbols[1] as usize] = 2;
-            code_lengths[symbols[2] as usize] = 2;
-        }
-        4 => {
-            let tree_select = reader.read_bit()?;
-            if tree_select {
-                code_lengths[symbols[0] as usize] = 2;
-                code_lengths[symbols[1] as usize] = 2;
-                code_lengths[symbols[2] as usize] = 2;
-                code_lengths[symbols[3] as usize] = 2;
+    let code_lengths: &[u8] = match num_symbols {
+        1 => &[1],
+        2 => &[1, 1],
+        3 => &[1, 2, 2],
+        _ => {
+            if reader.read_bit()? {
+                &[1, 2, 3, 3]
             } else {
-                code_lengths[symbols[0] as usize] = 1;
-                code_lengths[symbols[1] as usize] = 2;
-                code_lengths[symbols[2] as usize] = 3;
-                code_lengths[symbols[3] as usize] = 3;
+                &[2, 2, 2, 2]
             }
         }
-        _ => return Err(BrotliError::InvalidHuffman),
-    }
+    };
 
-    HuffmanDecoder::from_lengths(&code_lengths)
+    let mut lengths = vec![0u8; alphabet_size];
+    for (&symbol, &len) in symbols.iter().zip(code_lengths) {
+        lengths[symbol] = len;
+    }
+    HuffmanDecoder::from_lengths(&lengths)
 }
 
-/// Read complex prefix code
-fn read_complex_prefix_code(reader: &mut BitReader, alphabet_size: u16) -> BrotliResult<HuffmanDecoder> {
-    // Code length code order
-    const CODE_LENGTH_ORDER: [u8; 18] = [
-        1, 2, 3, 4, 0, 5, 17, 6, 16, 7, 8, 9, 10, 11, 12, 13, 14, 15
-    ];
-
-    let hskip = reader.read_bits(2)?;
-    
-    let mut code_length_lengths = [0u8; 18];
-    let mut space = 32;
+/// Order in which code length code lengths are transmitted
+const CODE_LENGTH_CODE_ORDER: [usize; 18] = [1, 2, 3, 4, 0, 5, 17, 6, 16, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+/// Bits consumed by the fixed code for code length code lengths (4-bit peek)
+const CODE_LENGTH_PREFIX_LENGTH: [u8; 16] = [2, 2, 2, 3, 2, 2, 2, 4, 2, 2, 2, 3, 2, 2, 2, 4];
+
+/// Values of the fixed code for code length code lengths (4-bit peek)
+const CODE_LENGTH_PREFIX_VALUE: [u8; 16] = [0, 4, 3, 2, 0, 4, 3, 1, 0, 4, 3, 2, 0, 4, 3, 5];
+
+/// Read complex prefix code (code lengths themselves prefix coded)
+fn read_complex_prefix_code(
+    reader: &mut BitReader,
+    alphabet_size: usize,
+    skip: usize,
+) -> BrotliResult<HuffmanDecoder> {
+    // Code lengths of the code length alphabet
+    let mut cl_lengths = [0u8; 18];
+    let mut space = 32i32;
     let mut num_codes = 0;
+    for &symbol in &CODE_LENGTH_CODE_ORDER[skip..] {
+        let (bits, available) = reader.peek_bits(4);
+        let len = CODE_LENGTH_PREFIX_LENGTH[bits as usize];
+        if len > available {
+            return Err(BrotliError::UnexpectedEof);
+        }
+        reader.consume(len);
 
-    for i in hskip as usize..18 {
-        let idx = CODE_LENGTH_ORDER[i] as usize;
-        
-        // Read code length length (variable length)
-        let len = read_code_length_code(reader)?;
-        code_length_lengths[idx] = len;
-        
-        if len != 0 {
-            space -= 32 >> len;
+        let value = CODE_LENGTH_PREFIX_VALUE[bits as usize];
+        cl_lengths[symbol] = value;
+        if value != 0 {
+            space -= 32 >> value;
             num_codes += 1;
             if space <= 0 {
                 break;
             }
         }
     }
-
-    // Build decoder for code lengths
-    let cl_decoder = HuffmanDecoder::from_lengths(&code_length_lengths)?;
-
-    // Read actual code lengths
-    let mut code_lengths = vec![0u8; alphabet_size as usize];
-    let mut i = 0usize;
-    let mut prev_code_len = 8u8;
-    let repeat_code_len = 0u8;
-
-    while i < alphabet_size as usize {
-        let sym = cl_decoder.decode(reader)?;
-        
-        match sym {
-            0..=15 => {
-                // Literal code length
-                code_lengths[i] = sym as u8;
-                if sym != 0 {
-                    prev_code_len = sym as u8;
-                }
-                i += 1;
-            }
-            16 => {
-                // Repeat previous
-                let extra = reader.read_bits(2)? as usize + 3;
-                for _ in 0..extra {
-                    if i >= alphabet_size as usize {
-                        break;
-                    }
-                    code_lengths[i] = prev_code_len;
-                    i += 1;
-                }
-            }
-            17 => {
-                // Repeat zero (short)
-                let extra = reader.read_bits(3)? as usize + 3;
-                i += extra;
+    if num_codes != 1 && space != 0 {
+        return Err(BrotliError::InvalidHuffman);
+    }
+    let cl_decoder = HuffmanDecoder::from_lengths(&cl_lengths)?;
+
+    // Symbol code lengths, with run-length codes 16 (repeat) and 17 (zeros)
+    let mut lengths = vec![0u8; alphabet_size];
+    let mut symbol = 0usize;
+    let mut prev_len = 8u8;
+    let mut repeat = 0usize;
+    let mut repeat_len = 0u8;
+    let mut space = 32768i32;
+    while symbol < alphabet_size && space > 0 {
+        let code = cl_decoder.decode(reader)? as u8;
+        if code < 16 {
+            repeat = 0;
+            lengths[symbol] = code;
+            symbol += 1;
+            if code != 0 {
+                prev_len = code;
+                space -= 32768 >> code;
             }
-            _ => return Err(BrotliError::InvalidHuffman),
+            continue;
         }
-    }
-
-    HuffmanDecoder::from_lengths(&code_lengths)
-}
 
-/// Read a code length code (0-5 variable length encoding)
-fn read_code_length_code(reader: &mut BitReader) -> BrotliResult<u8> {
-    // 0: 00
-    // 1: 01  
-    // 2: 10
-    // 3: 1100
-    // 4: 1101
-    // 5: 111x
-    let b0 = reader.read_bit()?;
-    if !b0 {
-        let b1 = reader.read_bit()?;
-        return Ok(if b1 { 1 } else { 0 });
-    }
-    let b1 = reader.read_bit()?;
-    if !b1 {
-        return Ok(2);
+        let (extra_bits, new_len) = if code == 16 { (2, prev_len) } else { (3, 0) };
+        if repeat_len != new_len {
+            repeat = 0;
+            repeat_len = new_len;
+        }
+        let old_repeat = repeat;
+        if repeat > 0 {
+            repeat = (repeat - 2) << extra_bits;
+        }
+        repeat += reader.read_bits(extra_bits)? as usize + 3;
+        let delta = repeat - old_repeat;
+        if symbol + delta > alphabet_size {
+            return Err(BrotliError::InvalidHuffman);
+        }
+        lengths[symbol..symbol + delta].fill(repeat_len);
+        symbol += delta;
+        if repeat_len != 0 {
+            space -= (delta as i32) << (15 - repeat_len);
+        }
     }
-    let b2 = reader.read_bit()?;
-    if !b2 {
-        let b3 = reader.read_bit()?;
-        return Ok(if b3 { 4 } else { 3 });
+    if space != 0 {
+        return Err(BrotliError::InvalidHuffman);
     }
-    let b3 = reader.read_bit()?;
-    Ok(if b3 { 5 } else { 5 })
+
+    HuffmanDecoder::from_lengths(&lengths)
 }
 
 /// Read prefix code (either simple or complex)
-fn read_prefix_code(reader: &mut BitReader, alphabet_size: u16) -> BrotliResult<HuffmanDecoder> {
-    let simple = reader.read_bit()?;
-    if simple {
+fn read_prefix_code(reader: &mut BitReader, alphabet_size: usize) -> BrotliResult<HuffmanDecoder> {
+    let skip = reader.read_bits(2)? as usize;
+    if skip == 1 {
         read_simple_prefix_code(reader, alphabet_size)
     } else {
-        read_complex_prefix_code(reader, alphabet_size)
+        read_complex_prefix_code(reader, alphabet_size, skip)
     }
 }
 
 // ============================================================================
-// Block Types
+// Block Types and Lengths
 // ============================================================================
 
-/// Block type info for each category
+/// Block length prefix codes: (base, extra bits)
+const BLOCK_LENGTH_PREFIX: [(u32, u8); 26] = [
+    (1, 2), (5, 2), (9, 2), (13, 2), (17, 3), (25, 3), (33, 3), (41, 3),
+    (49, 4), (65, 4), (81, 4), (97, 4), (113, 5), (145, 5), (177, 5), (209, 5),
+    (241, 6), (305, 6), (369, 7), (497, 8), (753, 9), (1265, 10), (2289, 11), (4337, 12),
+    (8433, 13), (16625, 24),
+];
+
+/// Block switching state for one category (literal, command or distance)
 struct BlockTypeInfo {
-    num_types: u8,
-    current_type: u8,
-    prev_type1: u8,
-    prev_type2: u8,
-    block_count: u32,
-    type_decoder: Option<HuffmanDecoder>,
-    count_decoder: Option<HuffmanDecoder>,
+    /// Number of block types (NBLTYPES)
+    count: usize,
+    type_code: Option<HuffmanDecoder>,
+    length_code: Option<HuffmanDecoder>,
+    /// Current block type
+    current: usize,
+    /// Second-to-last block type
+    previous: usize,
+    /// Symbols left in the current block
+    remaining: u32,
 }
 
 impl BlockTypeInfo {
-    fn new() -> Self {
-        Self {
-            num_types: 1,
-            current_type: 0,
-            prev_type1: 1,
-            prev_type2: 0,
-            block_count: 0,
-            type_decoder: None,
-            count_decoder: None,
-        }
-    }
-
-    fn read_header(&mut self, reader: &mut BitReader) -> BrotliResult<()> {
-        self.num_types = read_variable_length(reader, 8)? as u8 + 1;
-        
-        if self.num_types > 1 {
-            self.type_decoder = Some(read_prefix_code(reader, self.num_types as u16 + 2)?);
-            self.count_decoder = Some(read_prefix_code(reader, 26)?);
-            self.block_count = self.read_block_count(reader)?;
-        } else {
-            self.block_count = u32::MAX;
+    fn read(reader: &mut BitReader) -> BrotliResult<Self> {
+        let count = read_varlen_u8(reader)? as usize + 1;
+        let mut info = Self {
+            count,
+            type_code: None,
+            length_code: None,
+            current: 0,
+            previous: 1,
+            remaining: u32::MAX,
+        };
+        if count >= 2 {
+            info.type_code = Some(read_prefix_code(reader, count + 2)?);
+            let length_code = read_prefix_code(reader, BLOCK_LENGTH_PREFIX.len())?;
+            info.remaining = read_block_length(reader, &length_code)?;
+            info.length_code = Some(length_code);
         }
-        
-        Ok(())
+        Ok(info)
     }
 
-    fn read_block_count(&self, reader: &mut BitReader) -> BrotliResult<u32> {
-        if let Some(ref decoder) = self.count_decoder {
-            let code = decoder.decode(reader)?;
-            read_block_count_value(reader, code)
-        } else {
-            Ok(u32::MAX)
+    /// Advance by one symbol, switching blocks when the current one runs out
+    #[inline]
+    fn next(&mut self, reader: &mut BitReader) -> BrotliResult<usize> {
+        if self.remaining == 0 {
+            self.switch(reader)?;
         }
+        self.remaining -= 1;
+        Ok(self.current)
     }
 
-    fn maybe_switch_type(&mut self, reader: &mut BitReader) -> BrotliResult<()> {
-        if self.num_types <= 1 {
-            return Ok(());
-        }
-
-        self.block_count -= 1;
-        if self.block_count == 0 {
-            if let Some(ref decoder) = self.type_decoder {
-                let code = decoder.decode(reader)?;
-                let new_type = match code {
-                    0 => self.prev_type1,
-                    1 => (self.current_type + 1) % self.num_types,
-                    _ => (code - 2) as u8,
-                };
-                self.prev_type2 = self.prev_type1;
-                self.prev_type1 = self.current_type;
-                self.current_type = new_type;
-                self.block_count = self.read_block_count(reader)?;
-            }
+    fn switch(&mut self, reader: &mut BitReader) -> BrotliResult<()> {
+        let (Some(type_code), Some(length_code)) = (&self.type_code, &self.length_code) else {
+            return Err(BrotliError::InvalidBlockType);
+        };
+        let block_type = match type_code.decode(reader)? as usize {
+            0 => self.previous,
+            1 => (self.current + 1) % self.count,
+            code => code - 2,
+        };
+        if block_type >= self.count {
+            return Err(BrotliError::InvalidBlockType);
         }
+        self.previous = self.current;
+        self.current = block_type;
+        self.remaining = read_block_length(reader, length_code)?;
         Ok(())
     }
 }
 
-/// Read block count value
-fn read_block_count_value(reader: &mut BitReader, code: u16) -> BrotliResult<u32> {
-    const BLOCK_COUNT_EXTRA: [u8; 26] = [
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        2, 3, 4, 5, 6, 7, 8, 9, 12, 15
-    ];
-    const BLOCK_COUNT_BASE: [u32; 26] = [
-        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
-        17, 21, 29, 45, 77, 141, 269, 525, 1037, 5133
-    ];
-
-    let extra = reader.read_bits(BLOCK_COUNT_EXTRA[code as usize])?;
-    Ok(BLOCK_COUNT_BASE[code as usize] + extra)
-}
-
-/// Read variable length number
-fn read_variable_length(reader: &mut BitReader, max_bits: u8) -> BrotliResult<u32> {
-    if !reader.read_bit()? {
-        return Ok(0);
-    }
-    
-    let mut n = 0u8;
-    while n < max_bits && reader.read_bit()? {
-        n += 1;
-    }
-    
-    if n == 0 {
-        Ok(1)
-    } else {
-        let extra = reader.read_bits(n)?;
-        Ok((1 << n) + extra)
-    }
+fn read_block_length(reader: &mut BitReader, code: &HuffmanDecoder) -> BrotliResult<u32> {
+    let (base, extra) = BLOCK_LENGTH_PREFIX[code.decode(reader)? as usize];
+    Ok(base + reader.read_bits(extra)?)
 }
 
 // ============================================================================
@@ -623,166 +531,292 @@ impl From<u8> for ContextMode {
     }
 }
 
-/// Calculate literal context ID
+/// Calculate literal context ID from the two previous bytes
+#[inline]
 fn literal_context_id(prev1: u8, prev2: u8, mode: ContextMode) -> u8 {
     match mode {
         ContextMode::Lsb6 => prev1 & 0x3F,
         ContextMode::Msb6 => prev1 >> 2,
-        ContextMode::Utf8 => UTF8_CONTEXT_LUT[prev1 as usize],
+        ContextMode::Utf8 => {
+            UTF8_CONTEXT_LUT_P1[prev1 as usize] | UTF8_CONTEXT_LUT_P2[prev2 as usize]
+        }
         ContextMode::Signed => {
-            let lut0 = SIGNED_CONTEXT_LUT[(prev1 >> 3) as usize];
-            let lut1 = SIGNED_CONTEXT_LUT[(prev2 >> 3) as usize];
-            (lut0 << 3) | lut1
+            (SIGNED_CONTEXT_LUT[prev1 as usize] << 3) | SIGNED_CONTEXT_LUT[prev2 as usize]
         }
     }
 }
 
-/// UTF-8 context lookup table
-const UTF8_CONTEXT_LUT: [u8; 256] = {
-    let mut lut = [0u8; 256];
+/// Distance context from copy length
+#[inline]
+fn distance_context(copy_len: usize) -> usize {
+    if copy_len > 4 { 3 } else { copy_len - 2 }
+}
+
+/// Read a context map mapping `size` contexts to `num_trees` prefix codes
+fn read_context_map(reader: &mut BitReader, size: usize, num_trees: usize) -> BrotliResult<Vec<u8>> {
+    let mut map = vec![0u8; size];
+    if num_trees < 2 {
+        return Ok(map);
+    }
+
+    let rle_max = if reader.read_bit()? {
+        reader.read_bits(4)? as usize + 1
+    } else {
+        0
+    };
+    let code = read_prefix_code(reader, num_trees + rle_max)?;
+
     let mut i = 0;
-    while i < 256 {
-        lut[i] = if i < 0x80 {
-            // ASCII
-            if i < 0x20 || i == 0x7F { 0 } // Control
-            else if (i as u8).is_ascii_whitespace() { 1 } // Whitespace
-            else { 2 } // Other ASCII
-        } else if i < 0xC0 {
-            3 // Continuation byte
+    while i < size {
+        let symbol = code.decode(reader)? as usize;
+        if symbol == 0 {
+            i += 1;
+        } else if symbol <= rle_max {
+            let run = (1usize << symbol) + reader.read_bits(symbol as u8)? as usize;
+            if i + run > size {
+                return Err(BrotliError::InvalidStream);
+            }
+            i += run;
         } else {
-            4 // Start byte
-        };
-        i += 1;
+            map[i] = (symbol - rle_max) as u8;
+            i += 1;
+        }
     }
-    lut
-};
 
-/// Signed context lookup (for binary data)
-const SIGNED_CONTEXT_LUT: [u8; 32] = [
-    0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3,
-    4, 4, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7,
-];
+    // Inverse move-to-front transform
+    if reader.read_bit()? {
+        let mut mtf: [u8; 256] = std::array::from_fn(|i| i as u8);
+        for value in map.iter_mut() {
+            let index = *value as usize;
+            let v = mtf[index];
+            *value = v;
+            mtf.copy_within(0..index, 1);
+            mtf[0] = v;
+        }
+    }
 
-/// Distance context from insert/copy length
-fn distance_context(copy_len: u32) -> u8 {
-    if copy_len > 4 { 3 } else { copy_len.saturating_sub(2) as u8 }
+    Ok(map)
 }
 
 // ============================================================================
 // Insert and Copy Length Decoding
 // ============================================================================
 
-/// Decode insert and copy lengths
-fn decode_insert_copy(reader: &mut BitReader, decoder: &HuffmanDecoder) -> BrotliResult<(u32, u32)> {
-    let code = decoder.decode(reader)?;
-    
-    // Insert/copy length tables from RFC 7932
-    const INSERT_LENGTH_CODE_BASE: [u32; 24] = [
-        0, 1, 2, 3, 4, 5, 6, 8, 10, 14, 18, 26,
-        34, 50, 66, 98, 130, 194, 322, 578, 1090, 2114, 6210, 22594
-    ];
-    const INSERT_LENGTH_EXTRA_BITS: [u8; 24] = [
-        0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 3, 3,
-        4, 4, 5, 5, 6, 7, 8, 9, 10, 12, 14, 24
-    ];
-    const COPY_LENGTH_CODE_BASE: [u32; 24] = [
-        2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 14, 18,
-        22, 30, 38, 54, 70, 102, 134, 198, 326, 582, 1094, 2118
-    ];
-    const COPY_LENGTH_EXTRA_BITS: [u8; 24] = [
-        0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2,
-        3, 3, 4, 4, 5, 5, 6, 7, 8, 9, 10, 24
-    ];
-
-    let insert_code = (code >> 0) & 0x1F;
-    let copy_code = (code >> 5) & 0x1F;
-    
-    let insert_code = (insert_code as usize).min(23);
-    let copy_code = (copy_code as usize).min(23);
-
-    let insert_extra = reader.read_bits(INSERT_LENGTH_EXTRA_BITS[insert_code])?;
-    let copy_extra = reader.read_bits(COPY_LENGTH_EXTRA_BITS[copy_code])?;
-
-    let insert_len = INSERT_LENGTH_CODE_BASE[insert_code] + insert_extra;
-    let copy_len = COPY_LENGTH_CODE_BASE[copy_code] + copy_extra;
-
-    Ok((insert_len, copy_len))
+/// Insert length codes: (base, extra bits)
+const INSERT_LENGTH_PREFIX: [(u32, u8); 24] = [
+    (0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0), (6, 1), (8, 1),
+    (10, 2), (14, 2), (18, 3), (26, 3), (34, 4), (50, 4), (66, 5), (98, 5),
+    (130, 6), (194, 7), (322, 8), (578, 9), (1090, 10), (2114, 12), (6210, 14), (22594, 24),
+];
+
+/// Copy length codes: (base, extra bits)
+const COPY_LENGTH_PREFIX: [(u32, u8); 24] = [
+    (2, 0), (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0),
+    (10, 1), (12, 1), (14, 2), (18, 2), (22, 3), (30, 3), (38, 4), (54, 4),
+    (70, 5), (102, 5), (134, 6), (198, 7), (326, 8), (582, 9), (1094, 10), (2118, 24),
+];
+
+/// Insert and copy code offsets for each 64-symbol cell of the command alphabet
+const COMMAND_CELLS: [(usize, usize); 11] = [
+    (0, 0), (0, 8), (0, 0), (0, 8), (8, 0), (8, 8),
+    (0, 16), (16, 0), (8, 16), (16, 8), (16, 16),
+];
+
+/// Decoded insert-and-copy command
+struct Command {
+    insert_len: usize,
+    copy_len: usize,
+    /// The command reuses the last distance without reading a distance code
+    implicit_distance: bool,
+}
+
+/// Decode insert and copy lengths from a command symbol
+fn read_command(reader: &mut BitReader, code: &HuffmanDecoder) -> BrotliResult<Command> {
+    let symbol = code.decode(reader)? as usize;
+    let (insert_offset, copy_offset) = COMMAND_CELLS[symbol >> 6];
+    let (insert_base, insert_extra) = INSERT_LENGTH_PREFIX[insert_offset + ((symbol >> 3) & 7)];
+    let (copy_base, copy_extra) = COPY_LENGTH_PREFIX[copy_offset + (symbol & 7)];
+
+    let insert_len = insert_base + reader.read_bits(insert_extra)?;
+    let copy_len = copy_base + reader.read_bits(copy_extra)?;
+
+    Ok(Command {
+        insert_len: insert_len as usize,
+        copy_len: copy_len as usize,
+        implicit_distance: symbol < 128,
+    })
 }
 
 // ============================================================================
 // Distance Decoding
 // ============================================================================
 
-/// Distance ring buffer
+/// Ring of the four most recent distances
 struct DistanceRing {
-    ring: [u32; 4],
+    ring: [usize; 4],
     pos: usize,
 }
 
 impl DistanceRing {
     fn new() -> Self {
         Self {
-            ring: [4, 11, 15, 16], // Default values per spec
-            pos: 0,
+            // Initial distances 4, 11, 15, 16 (most recent first)
+            ring: [16, 15, 11, 4],
+            pos: 3,
         }
     }
 
-    fn push(&mut self, distance: u32) {
-        self.ring[self.pos] = distance;
+    fn push(&mut self, distance: usize) {
         self.pos = (self.pos + 1) & 3;
+        self.ring[self.pos] = distance;
+    }
+
+    /// Get the nth most recent distance (0 = last)
+    fn get(&self, n: usize) -> usize {
+        self.ring[(self.pos + 4 - n) & 3]
     }
+}
 
-    fn get(&self, idx: usize) -> u32 {
-        self.ring[(self.pos + 3 - idx) & 3]
+/// Distance parameters of a meta-block
+struct DistanceParams {
+    postfix_bits: u32,
+    direct_codes: usize,
+}
+
+impl DistanceParams {
+    fn alphabet_size(&self) -> usize {
+        16 + self.direct_codes + (48 << self.postfix_bits)
     }
 }
 
-/// Decode distance
-fn decode_distance(
+/// Translate a distance symbol to a distance
+fn read_distance(
     reader: &mut BitReader,
-    decoder: &HuffmanDecoder,
+    code: usize,
+    params: &DistanceParams,
     ring: &DistanceRing,
-    postfix_bits: u8,
-    direct_codes: u32,
-) -> BrotliResult<u32> {
-    let code = decoder.decode(reader)?;
-    
-    if code == 0 {
-        return Ok(ring.get(0));
+) -> BrotliResult<usize> {
+    if code < 16 {
+        const RING_INDEX: [usize; 16] = [0, 1, 2, 3, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1];
+        const RING_DELTA: [isize; 16] = [0, 0, 0, 0, -1, 1, -2, 2, -3, 3, -1, 1, -2, 2, -3, 3];
+        let distance = ring.get(RING_INDEX[code]) as isize + RING_DELTA[code];
+        if distance <= 0 {
+            return Err(BrotliError::InvalidDistance);
+        }
+        return Ok(distance as usize);
     }
-    if code == 1 {
-        return Ok(ring.get(1));
+
+    if code < 16 + params.direct_codes {
+        return Ok(code - 15);
     }
-    if code == 2 {
-        return Ok(ring.get(2));
+
+    let postfix_bits = params.postfix_bits;
+    let adjusted = code - params.direct_codes - 16;
+    let ndistbits = 1 + (adjusted >> (postfix_bits + 1));
+    let hcode = adjusted >> postfix_bits;
+    let lcode = adjusted & ((1 << postfix_bits) - 1);
+    let offset = ((2 + (hcode & 1)) << ndistbits) - 4;
+    let extra = reader.read_bits(ndistbits as u8)? as usize;
+
+    Ok(((offset + extra) << postfix_bits) + lcode + params.direct_codes + 1)
+}
+
+// ============================================================================
+// Static Dictionary
+// ============================================================================
+
+/// Built-in dictionary of RFC 7932 Appendix A
+static DICTIONARY: &[u8] = include_bytes!("brotli_dictionary.bin");
+
+/// Index bits per word length (lengths 4..=24 are present)
+const DICTIONARY_SIZE_BITS: [u8; 25] = [
+    0, 0, 0, 0, 10, 10, 11, 11, 10, 10, 10, 10, 10, 9, 9, 8, 7, 7, 8, 7, 7, 6, 6, 5, 5,
+];
+
+/// Byte offset of the words of each length
+const DICTIONARY_OFFSETS: [usize; 25] = {
+    let mut offsets = [0usize; 25];
+    let mut len = 4;
+    while len < 24 {
+        offsets[len + 1] = offsets[len] + (len << DICTIONARY_SIZE_BITS[len]);
+        len += 1;
     }
-    if code == 3 {
-        return Ok(ring.get(3));
+    offsets
+};
+
+/// Word transform applied between prefix and suffix
+#[derive(Debug, Clone, Copy)]
+enum WordTransform {
+    Identity,
+    OmitLast(u8),
+    UppercaseFirst,
+    UppercaseAll,
+    OmitFirst(u8),
+}
+
+/// Uppercase the UTF-8 character at `i`, returning its byte length
+fn uppercase_at(word: &mut [u8], i: usize) -> usize {
+    let c = word[i];
+    if c < 0xC0 {
+        word[i] = c.to_ascii_uppercase();
+        1
+    } else if c < 0xE0 {
+        if let Some(b) = word.get_mut(i + 1) {
+            *b ^= 32;
+        }
+        2
+    } else {
+        if let Some(b) = word.get_mut(i + 2) {
+            *b ^= 5;
+        }
+        3
     }
-    
-    // Transform codes 4-15 (distance modifications)
-    if code < 16 {
-        let idx = ((code - 4) / 2) as usize;
-        let sign = (code & 1) == 0;
-        let base = ring.get(idx);
-        return Ok(if sign { base + 1 } else { base.saturating_sub(1) });
+}
+
+/// Append a transformed dictionary word, returning the number of bytes written
+fn append_dictionary_word(
+    output: &mut Vec<u8>,
+    copy_len: usize,
+    word_id: usize,
+) -> BrotliResult<usize> {
+    let size_bits = DICTIONARY_SIZE_BITS[copy_len];
+    if size_bits == 0 {
+        return Err(BrotliError::InvalidDistance);
+    }
+    let index = word_id & ((1 << size_bits) - 1);
+    let transform_id = word_id >> size_bits;
+    let Some(&(prefix, transform, suffix)) = TRANSFORMS.get(transform_id) else {
+        return Err(BrotliError::InvalidDistance);
+    };
+
+    let offset = DICTIONARY_OFFSETS[copy_len] + index * copy_len;
+    let mut word = &DICTIONARY[offset..offset + copy_len];
+    match transform {
+        WordTransform::OmitFirst(n) => word = &word[(n as usize).min(word.len())..],
+        WordTransform::OmitLast(n) => word = &word[..word.len().saturating_sub(n as usize)],
+        _ => {}
     }
 
-    // Direct distance codes
-    let adjusted = code - 16;
-    if adjusted < direct_codes as u16 {
-        return Ok(adjusted as u32 + 1);
+    let start = output.len();
+    output.extend_from_slice(prefix);
+    let word_start = output.len();
+    output.extend_from_slice(word);
+    let word = &mut output[word_start..];
+    match transform {
+        WordTransform::UppercaseFirst if !word.is_empty() => {
+            uppercase_at(word, 0);
+        }
+        WordTransform::UppercaseAll => {
+            let mut i = 0;
+            while i < word.len() {
+                i += uppercase_at(word, i);
+            }
+        }
+        _ => {}
     }
+    output.extend_from_slice(suffix);
 
-    // Far distance
-    let ndistbits = 1 + ((adjusted - direct_codes as u16) >> (postfix_bits + 1));
-    let postfix_mask = (1u32 << postfix_bits) - 1;
-    let hcode = (adjusted - direct_codes as u16) as u32 - ((ndistbits as u32 - 1) << (postfix_bits + 1));
-    let lcode = hcode & postfix_mask;
-    let offset = ((2 + (hcode >> postfix_bits)) << ndistbits) - 4;
-    let extra = reader.read_bits(ndistbits as u8)?;
-    
-    Ok(((offset + extra) << postfix_bits) + lcode + direct_codes + 1)
+    Ok(output.len() - start)
 }
 
 // ============================================================================
@@ -791,13 +825,21 @@ fn decode_distance(
 
 /// Brotli decoder state
 pub struct BrotliDecoder {
-    window_bits: u8,
+    output_limit: usize,
 }
 
 impl BrotliDecoder {
     /// Create new decoder
     pub fn new() -> Self {
-        Self { window_bits: 22 }
+        Self {
+            output_limit: DEFAULT_OUTPUT_LIMIT,
+        }
+    }
+
+    /// Limit the decompressed size; larger streams fail with `OutputOverflow`
+    pub fn with_output_limit(mut self, limit: usize) -> Self {
+        self.output_limit = limit;
+        self
     }
 
     /// Decompress Brotli data
@@ -805,277 +847,207 @@ impl BrotliDecoder {
         self.decompress_with_dict(input, &[])
     }
 
-    /// Decompress with optional dictionary
+    /// Decompress with a custom dictionary that back-references may reach into
     pub fn decompress_with_dict(&mut self, input: &[u8], dict: &[u8]) -> BrotliResult<Vec<u8>> {
         let mut reader = BitReader::new(input);
-        let mut output = Vec::new();
-
-        // Read window size
-        let wbits = self.read_window_bits(&mut reader)?;
-        self.window_bits = wbits;
-        
-        let mut ring_buffer = RingBuffer::new(wbits.min(24)); // Limit for memory
-        let mut distance_ring = DistanceRing::new();
-        
-        // Initialize with dictionary
-        for &b in dict {
-            ring_buffer.push(b);
-        }
+        let window_bits = read_window_bits(&mut reader)?;
+        let max_backward = (1usize << window_bits) - 16;
+        let limit = dict.len().saturating_add(self.output_limit);
+
+        // The dictionary behaves as if it had been decoded before the stream
+        let mut output = dict.to_vec();
+        let mut distances = DistanceRing::new();
 
-        // Process meta-blocks
         loop {
             let is_last = reader.read_bit()?;
-            
-            if is_last && reader.is_empty() {
+            if is_last && reader.read_bit()? {
                 break;
             }
 
-            // Read meta-block length
-            let (mlen, is_empty) = self.read_meta_block_length(&mut reader)?;
-            
-            if is_empty {
-                if is_last {
-                    break;
+            let nibbles = match reader.read_bits(2)? {
+                3 => 0,
+                n => n as u8 + 4,
+            };
+
+            if nibbles == 0 {
+                // Metadata block: skipped
+                if is_last || reader.read_bit()? {
+                    return Err(BrotliError::InvalidStream);
+                }
+                let skip_bytes = reader.read_bits(2)? as u8;
+                let mut skip_len = 0usize;
+                for i in 0..skip_bytes {
+                    let byte = reader.read_bits(8)? as usize;
+                    if i + 1 == skip_bytes && skip_bytes > 1 && byte == 0 {
+                        return Err(BrotliError::InvalidStream);
+                    }
+                    skip_len |= byte << (8 * i);
+                }
+                if skip_bytes > 0 {
+                    skip_len += 1;
+                }
+                reader.align_to_byte()?;
+                for _ in 0..skip_len {
+                    reader.read_byte()?;
                 }
                 continue;
             }
 
-            // Check for uncompressed block
-            let is_uncompressed = if !is_last {
-                reader.read_bit()?
-            } else {
-                false
-            };
-
-            if is_uncompressed {
-                // Byte-align
-                while reader.bits_available % 8 != 0 {
-                    reader.drop_bits(1);
+            let mut length = 0usize;
+            for i in 0..nibbles {
+                let nibble = reader.read_bits(4)? as usize;
+                if i + 1 == nibbles && nibbles > 4 && nibble == 0 {
+                    return Err(BrotliError::InvalidStream);
                 }
-                
-                // Copy raw bytes
-                for _ in 0..mlen {
-                    let b = reader.read_bits(8)? as u8;
-                    output.push(b);
-                    ring_buffer.push(b);
+                length |= nibble << (4 * i);
+            }
+            let length = length + 1;
+            if output.len() + length > limit {
+                return Err(BrotliError::OutputOverflow);
+            }
+
+            if !is_last && reader.read_bit()? {
+                // Uncompressed meta-block
+                reader.align_to_byte()?;
+                output.reserve(length);
+                for _ in 0..length {
+                    output.push(reader.read_byte()?);
                 }
-            } else {
-                // Compressed block
-                self.decompress_meta_block(
-                    &mut reader,
-                    &mut output,
-                    &mut ring_buffer,
-                    &mut distance_ring,
-                    mlen,
-                )?;
+                continue;
             }
 
+            decode_compressed_block(&mut reader, &mut output, length, max_backward, &mut distances)?;
+
             if is_last {
                 break;
             }
         }
 
+        output.drain(..dict.len());
         Ok(output)
     }
+}
 
-    fn read_window_bits(&self, reader: &mut BitReader) -> BrotliResult<u8> {
-        let large = reader.read_bit()?;
-        if !large {
-            return Ok(16);
-        }
-
-        let bits = reader.read_bits(3)?;
-        if bits == 0 {
-            let extra = reader.read_bit()?;
-            return Ok(if extra { 10 } else { 17 });
-        }
-        
-        Ok(17 + bits as u8)
+/// Read the stream header's window size (WBITS)
+fn read_window_bits(reader: &mut BitReader) -> BrotliResult<u8> {
+    if !reader.read_bit()? {
+        return Ok(16);
     }
+    let n = reader.read_bits(3)? as u8;
+    if n != 0 {
+        return Ok(17 + n);
+    }
+    match reader.read_bits(3)? as u8 {
+        0 => Ok(17),
+        // Large-window Brotli is not part of RFC 7932
+        1 => Err(BrotliError::WindowTooLarge),
+        m => Ok(8 + m),
+    }
+}
 
-    fn read_meta_block_length(&self, reader: &mut BitReader) -> BrotliResult<(u32, bool)> {
-        let nibbles = reader.read_bits(2)?;
-        
-        if nibbles == 3 {
-            // Reserved or empty
-            let is_empty = reader.read_bit()?;
-            return Ok((0, is_empty));
-        }
+/// Decode one compressed meta-block of `length` bytes
+fn decode_compressed_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    length: usize,
+    max_backward: usize,
+    distances: &mut DistanceRing,
+) -> BrotliResult<()> {
+    let mut literal_blocks = BlockTypeInfo::read(reader)?;
+    let mut command_blocks = BlockTypeInfo::read(reader)?;
+    let mut distance_blocks = BlockTypeInfo::read(reader)?;
+
+    let postfix_bits = reader.read_bits(2)?;
+    let params = DistanceParams {
+        postfix_bits,
+        direct_codes: (reader.read_bits(4)? as usize) << postfix_bits,
+    };
 
-        let num_nibbles = (nibbles + 4) as usize;
-        let mut mlen = 0u32;
-        for i in 0..num_nibbles {
-            let nibble = reader.read_bits(4)?;
-            mlen |= nibble << (4 * i);
-        }
-        
-        Ok((mlen + 1, false))
+    let mut context_modes = Vec::with_capacity(literal_blocks.count);
+    for _ in 0..literal_blocks.count {
+        context_modes.push(ContextMode::from(reader.read_bits(2)? as u8));
     }
 
-    fn decompress_meta_block(
-        &mut self,
-        reader: &mut BitReader,
-        output: &mut Vec<u8>,
-        ring_buffer: &mut RingBuffer,
-        distance_ring: &mut DistanceRing,
-        mlen: u32,
-    ) -> BrotliResult<()> {
-        // Read block types
-        let mut literal_types = BlockTypeInfo::new();
-        let mut iclen_types = BlockTypeInfo::new();
-        let mut dist_types = BlockTypeInfo::new();
-
-        literal_types.read_header(reader)?;
-        iclen_types.read_header(reader)?;
-        dist_types.read_header(reader)?;
-
-        // Distance parameters
-        let postfix_bits = reader.read_bits(2)? as u8;
-        let direct_codes_raw = reader.read_bits(4)?;
-        let direct_codes = direct_codes_raw << postfix_bits;
-
-        // Context modes for literals
-        let mut context_modes = Vec::with_capacity(literal_types.num_types as usize);
-        for _ in 0..literal_types.num_types {
-            let mode = reader.read_bits(2)? as u8;
-            context_modes.push(ContextMode::from(mode));
-        }
-
-        // Read context maps
-        let num_literal_ctxs = 64 * literal_types.num_types as u32;
-        let num_dist_ctxs = 4 * dist_types.num_types as u32;
-        
-        let literal_ctx_map = self.read_context_map(reader, num_literal_ctxs)?;
-        let dist_ctx_map = self.read_context_map(reader, num_dist_ctxs)?;
-
-        // Read Huffman trees
-        let num_literal_trees = literal_ctx_map.iter().max().copied().unwrap_or(0) as usize + 1;
-        let mut literal_trees = Vec::with_capacity(num_literal_trees);
-        for _ in 0..num_literal_trees {
-            literal_trees.push(read_prefix_code(reader, 256)?);
+    let literal_trees = read_varlen_u8(reader)? as usize + 1;
+    let literal_map = read_context_map(reader, literal_blocks.count * 64, literal_trees)?;
+    let distance_trees = read_varlen_u8(reader)? as usize + 1;
+    let distance_map = read_context_map(reader, distance_blocks.count * 4, distance_trees)?;
+
+    let literal_codes = (0..literal_trees)
+        .map(|_| read_prefix_code(reader, 256))
+        .collect::<BrotliResult<Vec<_>>>()?;
+    let command_codes = (0..command_blocks.count)
+        .map(|_| read_prefix_code(reader, 704))
+        .collect::<BrotliResult<Vec<_>>>()?;
+    let distance_codes = (0..distance_trees)
+        .map(|_| read_prefix_code(reader, params.alphabet_size()))
+        .collect::<BrotliResult<Vec<_>>>()?;
+
+    let mut remaining = length;
+    while remaining > 0 {
+        let command_type = command_blocks.next(reader)?;
+        let command = read_command(reader, &command_codes[command_type])?;
+
+        if command.insert_len > remaining {
+            return Err(BrotliError::InvalidStream);
         }
-
-        let num_iclen_trees = iclen_types.num_types as usize;
-        let mut iclen_trees = Vec::with_capacity(num_iclen_trees);
-        for _ in 0..num_iclen_trees {
-            iclen_trees.push(read_prefix_code(reader, 704)?);
+        for _ in 0..command.insert_len {
+            let block_type = literal_blocks.next(reader)?;
+            let len = output.len();
+            let prev1 = if len > 0 { output[len - 1] } else { 0 };
+            let prev2 = if len > 1 { output[len - 2] } else { 0 };
+            let context = literal_context_id(prev1, prev2, context_modes[block_type]) as usize;
+            let tree = literal_map[block_type * 64 + context] as usize;
+            output.push(literal_codes[tree].decode(reader)? as u8);
         }
-
-        let num_dist_trees = dist_ctx_map.iter().max().copied().unwrap_or(0) as usize + 1;
-        let num_dist_codes = 16 + direct_codes + (48 << postfix_bits);
-        let mut dist_trees = Vec::with_capacity(num_dist_trees);
-        for _ in 0..num_dist_trees {
-            dist_trees.push(read_prefix_code(reader, num_dist_codes as u16)?);
+        remaining -= command.insert_len;
+        if remaining == 0 {
+            break;
         }
 
-        // Decode data
-        let start_len = output.len();
-        let mut prev1 = 0u8;
-        let mut prev2 = 0u8;
-
-        while output.len() - start_len < mlen as usize {
-            iclen_types.maybe_switch_type(reader)?;
-            
-            let iclen_tree = &iclen_trees[iclen_types.current_type as usize];
-            let (insert_len, copy_len) = decode_insert_copy(reader, iclen_tree)?;
-
-            // Insert literals
-            for _ in 0..insert_len {
-                literal_types.maybe_switch_type(reader)?;
-                
-                let ctx = literal_context_id(prev1, prev2, context_modes[literal_types.current_type as usize]);
-                let tree_idx = literal_ctx_map[(literal_types.current_type as u32 * 64 + ctx as u32) as usize];
-                let literal_tree = &literal_trees[tree_idx as usize];
-                
-                let literal = literal_tree.decode(reader)? as u8;
-                output.push(literal);
-                ring_buffer.push(literal);
-                
-                prev2 = prev1;
-                prev1 = literal;
-            }
-
-            if output.len() - start_len >= mlen as usize {
-                break;
-            }
+        let (distance, explicit_code) = if command.implicit_distance {
+            (distances.get(0), 0)
+        } else {
+            let block_type = distance_blocks.next(reader)?;
+            let tree = distance_map[block_type * 4 + distance_context(command.copy_len)] as usize;
+            let code = distance_codes[tree].decode(reader)? as usize;
+            (read_distance(reader, code, &params, distances)?, code)
+        };
 
-            // Decode distance
-            dist_types.maybe_switch_type(reader)?;
-            
-            let dist_ctx = distance_context(copy_len);
-            let tree_idx = dist_ctx_map[(dist_types.current_type as u32 * 4 + dist_ctx as u32) as usize];
-            let dist_tree = &dist_trees[tree_idx as usize];
-            
-            let distance = decode_distance(reader, dist_tree, distance_ring, postfix_bits, direct_codes)?;
-            distance_ring.push(distance);
-
-            // Copy from history
-            if distance as usize > output.len() {
+        let max_distance = max_backward.min(output.len());
+        if distance > max_distance {
+            // Reference into the static dictionary
+            if !(4..=24).contains(&command.copy_len) {
                 return Err(BrotliError::InvalidDistance);
             }
-
-            for _ in 0..copy_len {
-                let byte = ring_buffer.get(distance as usize);
-                output.push(byte);
-                ring_buffer.push(byte);
-                prev2 = prev1;
-                prev1 = byte;
+            let written = append_dictionary_word(output, command.copy_len, distance - max_distance - 1)?;
+            if written > remaining {
+                return Err(BrotliError::InvalidStream);
             }
+            remaining -= written;
+            continue;
         }
 
-        Ok(())
-    }
-
-    fn read_context_map(&self, reader: &mut BitReader, size: u32) -> BrotliResult<Vec<u8>> {
-        if size == 0 {
-            return Ok(vec![0]);
-        }
-
-        let num_trees = read_variable_length(reader, 8)? as u8 + 1;
-        
-        if num_trees == 1 {
-            return Ok(vec![0; size as usize]);
-        }
-
-        let use_rle = reader.read_bit()?;
-        let mut rle_max = 0u8;
-        if use_rle {
-            while rle_max < 16 && reader.read_bit()? {
-                rle_max += 1;
-            }
+        if command.copy_len > remaining {
+            return Err(BrotliError::InvalidStream);
         }
-
-        let ctx_decoder = read_prefix_code(reader, num_trees as u16 + rle_max as u16)?;
-        
-        let mut map = Vec::with_capacity(size as usize);
-        while map.len() < size as usize {
-            let code = ctx_decoder.decode(reader)?;
-            
-            if code < num_trees as u16 {
-                map.push(code as u8);
-            } else {
-                // RLE zero
-                let extra_bits = code - num_trees as u16 + 1;
-                let run_len = (1 << extra_bits) + reader.read_bits(extra_bits as u8)?;
-                for _ in 0..run_len {
-                    map.push(0);
-                }
-            }
+        if explicit_code != 0 {
+            distances.push(distance);
         }
 
-        // IMTF decode
-        let mut mtf = (0..num_trees).collect::<Vec<_>>();
-        for val in map.iter_mut() {
-            let idx = *val as usize;
-            *val = mtf[idx];
-            if idx > 0 {
-                let v = mtf.remove(idx);
-                mtf.insert(0, v);
+        let start = output.len() - distance;
+        if distance >= command.copy_len {
+            output.extend_from_within(start..start + command.copy_len);
+        } else {
+            for i in 0..command.copy_len {
+                let byte = output[start + i];
+                output.push(byte);
             }
         }
-
-        Ok(map)
+        remaining -= command.copy_len;
     }
+
+    Ok(())
 }
 
 impl Default for BrotliDecoder {
@@ -1085,27 +1057,193 @@ impl Default for BrotliDecoder {
 }
 
 // ============================================================================
-// Public API
+// Tables
 // ============================================================================
 
-/// Decompress Brotli data
-pub fn decompress(input: &[u8]) -> BrotliResult<Vec<u8>> {
-    BrotliDecoder::new().decompress(input)
-}
+/// Word transforms of RFC 7932 Appendix B: (prefix, transform, suffix)
+const TRANSFORMS: [(&[u8], WordTransform, &[u8]); 121] = [
+    (b"", WordTransform::Identity, b""),
+    (b"", WordTransform::Identity, b" "),
+    (b" ", WordTransform::Identity, b" "),
+    (b"", WordTransform::OmitFirst(1), b""),
+    (b"", WordTransform::UppercaseFirst, b" "),
+    (b"", WordTransform::Identity, b" the "),
+    (b" ", WordTransform::Identity, b""),
+    (b"s ", WordTransform::Identity, b" "),
+    (b"", WordTransform::Identity, b" of "),
+    (b"", WordTransform::UppercaseFirst, b""),
+    (b"", WordTransform::Identity, b" and "),
+    (b"", WordTransform::OmitFirst(2), b""),
+    (b"", WordTransform::OmitLast(1), b""),
+    (b", ", WordTransform::Identity, b" "),
+    (b"", WordTransform::Identity, b", "),
+    (b" ", WordTransform::UppercaseFirst, b" "),
+    (b"", WordTransform::Identity, b" in "),
+    (b"", WordTransform::Identity, b" to "),
+    (b"e ", WordTransform::Identity, b" "),
+    (b"", WordTransform::Identity, b"\""),
+    (b"", WordTransform::Identity, b"."),
+    (b"", WordTransform::Identity, b"\">"),
+    (b"", WordTransform::Identity, b"\n"),
+    (b"", WordTransform::OmitLast(3), b""),
+    (b"", WordTransform::Identity, b"]"),
+    (b"", WordTransform::Identity, b" for "),
+    (b"", WordTransform::OmitFirst(3), b""),
+    (b"", WordTransform::OmitLast(2), b""),
+    (b"", WordTransform::Identity, b" a "),
+    (b"", WordTransform::Identity, b" that "),
+    (b" ", WordTransform::UppercaseFirst, b""),
+    (b"", WordTransform::Identity, b". "),
+    (b".", WordTransform::Identity, b""),
+    (b" ", WordTransform::Identity, b", "),
+    (b"", WordTransform::OmitFirst(4), b""),
+    (b"", WordTransform::Identity, b" with "),
+    (b"", WordTransform::Identity, b"'"),
+    (b"", WordTransform::Identity, b" from "),
+    (b"", WordTransform::Identity, b" by "),
+    (b"", WordTransform::OmitFirst(5), b""),
+    (b"", WordTransform::OmitFirst(6), b""),
+    (b" the ", WordTransform::Identity, b""),
+    (b"", WordTransform::OmitLast(4), b""),
+    (b"", WordTransform::Identity, b". The "),
+    (b"", WordTransform::UppercaseAll, b""),
+    (b"", WordTransform::Identity, b" on "),
+    (b"", WordTransform::Identity, b" as "),
+    (b"", WordTransform::Identity, b" is "),
+    (b"", WordTransform::OmitLast(7), b""),
+    (b"", WordTransform::OmitLast(1), b"ing "),
+    (b"", WordTransform::Identity, b"\n\t"),
+    (b"", WordTransform::Identity, b":"),
+    (b" ", WordTransform::Identity, b". "),
+    (b"", WordTransform::Identity, b"ed "),
+    (b"", WordTransform::OmitFirst(9), b""),
+    (b"", WordTransform::OmitFirst(7), b""),
+    (b"", WordTransform::OmitLast(6), b""),
+    (b"", WordTransform::Identity, b"("),
+    (b"", WordTransform::UppercaseFirst, b", "),
+    (b"", WordTransform::OmitLast(8), b""),
+    (b"", WordTransform::Identity, b" at "),
+    (b"", WordTransform::Identity, b"ly "),
+    (b" the ", WordTransform::Identity, b" of "),
+    (b"", WordTransform::OmitLast(5), b""),
+    (b"", WordTransform::OmitLast(9), b""),
+    (b" ", WordTransform::UppercaseFirst, b", "),
+    (b"", WordTransform::UppercaseFirst, b"\""),
+    (b".", WordTransform::Identity, b"("),
+    (b"", WordTransform::UppercaseAll, b" "),
+    (b"", WordTransform::UppercaseFirst, b"\">"),
+    (b"", WordTransform::Identity, b"=\""),
+    (b" ", WordTransform::Identity, b"."),
+    (b".com/", WordTransform::Identity, b""),
+    (b" the ", WordTransform::Identity, b" of the "),
+    (b"", WordTransform::UppercaseFirst, b"'"),
+    (b"", WordTransform::Identity, b". This "),
+    (b"", WordTransform::Identity, b","),
+    (b".", WordTransform::Identity, b" "),
+    (b"", WordTransform::UppercaseFirst, b"("),
+    (b"", WordTransform::UppercaseFirst, b"."),
+    (b"", WordTransform::Identity, b" not "),
+    (b" ", WordTransform::Identity, b"=\""),
+    (b"", WordTransform::Identity, b"er "),
+    (b" ", WordTransform::UppercaseAll, b" "),
+    (b"", WordTransform::Identity, b"al "),
+    (b" ", WordTransform::UppercaseAll, b""),
+    (b"", WordTransform::Identity, b"='"),
+    (b"", WordTransform::UppercaseAll, b"\""),
+    (b"", WordTransform::UppercaseFirst, b". "),
+    (b" ", WordTransform::Identity, b"("),
+    (b"", WordTransform::Identity, b"ful "),
+    (b" ", WordTransform::UppercaseFirst, b". "),
+    (b"", WordTransform::Identity, b"ive "),
+    (b"", WordTransform::Identity, b"less "),
+    (b"", WordTransform::UppercaseAll, b"'"),
+    (b"", WordTransform::Identity, b"est "),
+    (b" ", WordTransform::UppercaseFirst, b"."),
+    (b"", WordTransform::UppercaseAll, b"\">"),
+    (b" ", WordTransform::Identity, b"='"),
+    (b"", WordTransform::UppercaseFirst, b","),
+    (b"", WordTransform::Identity, b"ize "),
+    (b"", WordTransform::UppercaseAll, b"."),
+    (b"\xc2\xa0", WordTransform::Identity, b""),
+    (b" ", WordTransform::Identity, b","),
+    (b"", WordTransform::UppercaseFirst, b"=\""),
+    (b"", WordTransform::UppercaseAll, b"=\""),
+    (b"", WordTransform::Identity, b"ous "),
+    (b"", WordTransform::UppercaseAll, b", "),
+    (b"", WordTransform::UppercaseFirst, b"='"),
+    (b" ", WordTransform::UppercaseFirst, b","),
+    (b" ", WordTransform::UppercaseAll, b"=\""),
+    (b" ", WordTransform::UppercaseAll, b", "),
+    (b"", WordTransform::UppercaseAll, b","),
+    (b"", WordTransform::UppercaseAll, b"("),
+    (b"", WordTransform::UppercaseAll, b". "),
+    (b" ", WordTransform::UppercaseAll, b"."),
+    (b"", WordTransform::UppercaseAll, b"='"),
+    (b" ", WordTransform::UppercaseAll, b". "),
+    (b" ", WordTransform::UppercaseFirst, b"=\""),
+    (b" ", WordTransform::UppercaseAll, b"='"),
+    (b" ", WordTransform::UppercaseFirst, b"='"),
+];
 
-/// Decompress with dictionary (for WOFF2)
-pub fn decompress_with_dict(input: &[u8], dict: &[u8]) -> BrotliResult<Vec<u8>> {
-    BrotliDecoder::new().decompress_with_dict(input, dict)
-}
+/// UTF-8 context contribution of the previous byte
+const UTF8_CONTEXT_LUT_P1: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 4, 0, 0, 4, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    8, 12, 16, 12, 12, 20, 12, 16, 24, 28, 12, 12, 32, 12, 36, 12,
+    44, 44, 44, 44, 44, 44, 44, 44, 44, 44, 32, 32, 24, 40, 28, 12,
+    12, 48, 52, 52, 52, 48, 52, 52, 52, 48, 52, 52, 52, 52, 52, 48,
+    52, 52, 52, 52, 52, 48, 52, 52, 52, 52, 52, 24, 12, 28, 12, 12,
+    12, 56, 60, 60, 60, 56, 60, 60, 60, 56, 60, 60, 60, 60, 60, 56,
+    60, 60, 60, 60, 60, 56, 60, 60, 60, 60, 60, 24, 12, 28, 12, 0,
+    0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1,
+    0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1,
+    0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1,
+    0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1,
+    2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3,
+    2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3,
+    2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3,
+    2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3,
+];
 
-// ============================================================================
-// WOFF2 Transform Dictionary
-// ============================================================================
+/// UTF-8 context contribution of the byte before that
+const UTF8_CONTEXT_LUT_P2: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1,
+    1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1,
+    1, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+    3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 1, 1, 1, 1, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+];
 
-/// WOFF2 uses a custom dictionary derived from font data.
-/// This is a subset - the full 120KB dictionary would be included
-/// as a static binary blob in production.
-pub static WOFF2_DICTIONARY: &[u8] = &[];
+/// Signed context class (0-7) of a byte
+const SIGNED_CONTEXT_LUT: [u8; 256] = [
+    0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+    3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+    3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+    3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+    4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4,
+    4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4,
+    4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4,
+    4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4,
+    5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5,
+    5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5,
+    5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5,
+    6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 7,
+];
 
 // ============================================================================
 // Tests
@@ -1118,20 +1256,14 @@ mod tests {
     #[test]
     fn test_bit_reader_basic() {
         // 0b11010010 = bits 0-7: 0,1,0,0,1,0,1,1 (LSB first)
-        // 0b10110100 = bits 0-7: 0,0,1,0,1,1,0,1 (LSB first)
         let data = [0b11010010, 0b10110100];
         let mut reader = BitReader::new(&data);
-        
-        // Read bit 0 (LSB of first byte): 0
+
         assert_eq!(reader.read_bits(1).unwrap(), 0);
-        // Read bit 1: 1
         assert_eq!(reader.read_bits(1).unwrap(), 1);
-        // Read bits 2-3: 0b01 = 0,1 -> value 2 (bit 2 is 0, bit 3 is 0) -> actually 0b00
-        // Let me recalculate: 0b11010010 bits are:
-        // bit 0 = 0, bit 1 = 1, bit 2 = 0, bit 3 = 0, bit 4 = 1, bit 5 = 0, bit 6 = 1, bit 7 = 1
-        // After reading bits 0,1, we're at bit 2. Read 2 bits (2,3) = 0,0 -> 0b00 = 0
+        // Bits 2-3 are both 0
         assert_eq!(reader.read_bits(2).unwrap(), 0b00);
-        // Read 4 bits (4,5,6,7) = 1,0,1,1 -> 0b1101 = 13
+        // Bits 4-7 = 1,0,1,1 -> 0b1101
         assert_eq!(reader.read_bits(4).unwrap(), 0b1101);
     }
 
@@ -1139,41 +1271,40 @@ mod tests {
     fn test_huffman_single_symbol() {
         let lengths = [0, 1, 0];
         let decoder = HuffmanDecoder::from_lengths(&lengths).unwrap();
-        assert_eq!(decoder.num_symbols, 3);
+
+        // A lone symbol consumes no bits
+        let mut reader = BitReader::new(&[]);
+        assert_eq!(decoder.decode(&mut reader).unwrap(), 1);
     }
 
     #[test]
     fn test_huffman_two_symbols() {
         let lengths = [1, 1, 0];
         let decoder = HuffmanDecoder::from_lengths(&lengths).unwrap();
-        
+
         // Symbol 0 = 0, Symbol 1 = 1
         let data = [0b01010101];
         let mut reader = BitReader::new(&data);
-        
+
         assert_eq!(decoder.decode(&mut reader).unwrap(), 1);
         assert_eq!(decoder.decode(&mut reader).unwrap(), 0);
     }
 
     #[test]
-    fn test_ring_buffer() {
-        let mut rb = RingBuffer::new(4); // 16-byte buffer
-        
-        for i in 0..10u8 {
-            rb.push(i);
-        }
-        
-        assert_eq!(rb.get(1), 9);
-        assert_eq!(rb.get(2), 8);
-        assert_eq!(rb.get(10), 0);
+    fn test_huffman_rejects_incomplete_code() {
+        assert!(HuffmanDecoder::from_lengths(&[1, 2, 0]).is_err());
+        assert!(HuffmanDecoder::from_lengths(&[1, 1, 1]).is_err());
     }
 
     #[test]
     fn test_distance_ring() {
         let mut ring = DistanceRing::new();
+        assert_eq!(ring.get(0), 4);
+        assert_eq!(ring.get(3), 16);
+
         ring.push(100);
         ring.push(200);
-        
+
         assert_eq!(ring.get(0), 200);
         assert_eq!(ring.get(1), 100);
     }
@@ -1189,4 +1320,65 @@ mod tests {
         assert_eq!(literal_context_id(0x80, 0, ContextMode::Msb6), 0x20);
         assert_eq!(literal_context_id(0xFF, 0, ContextMode::Msb6), 0x3F);
     }
+
+    #[test]
+    fn test_dictionary_words() {
+        let mut out = Vec::new();
+        // First 4-byte word, identity transform
+        assert_eq!(append_dictionary_word(&mut out, 4, 0).unwrap(), 4);
+        assert_eq!(out, b"time");
+
+        // Transform 9 uppercases the first letter
+        out.clear();
+        append_dictionary_word(&mut out, 5, 9 << 10).unwrap();
+        assert_eq!(out, b"First");
+
+        // Transform 44 uppercases everything
+        out.clear();
+        append_dictionary_word(&mut out, 4, 44 << 10).unwrap();
+        assert_eq!(out, b"TIME");
+
+        assert!(append_dictionary_word(&mut out, 4, 121 << 10).is_err());
+    }
+
+    #[test]
+    fn test_decompress_empty() {
+        let mut decoder = BrotliDecoder::new();
+        assert!(decoder.decompress(&[0x06]).unwrap().is_empty());
+        assert!(decoder.decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_decompress_uncompressed_block() {
+        let mut input = vec![0x03, 0x06, 0x80];
+        input.extend_from_slice(b"Hello, WOFF2!");
+        input.push(0x03);
+        let mut decoder = BrotliDecoder::new();
+        assert_eq!(decoder.decompress(&input).unwrap(), b"Hello, WOFF2!");
+
+        // Custom dictionaries are not part of the output
+        assert_eq!(decoder.decompress_with_dict(&input, b"prefix").unwrap(), b"Hello, WOFF2!");
+
+        let mut limited = BrotliDecoder::new().with_output_limit(4);
+        assert!(matches!(limited.decompress(&input), Err(BrotliError::OutputOverflow)));
+    }
+
+    #[test]
+    fn test_decompress_text() {
+        let compressed = [
+            0x1b, 0x58, 0x00, 0x88, 0x8c, 0xd4, 0x46, 0xf5, 0xd4, 0x92, 0x34, 0x42, 0xa5, 0x43,
+            0xe4, 0x2d, 0xd5, 0xfb, 0x50, 0x94, 0x60, 0x26, 0x98, 0xcd, 0x6c, 0x1f, 0xa2, 0xf7,
+            0x81, 0xa6, 0x20, 0xdf, 0xd0, 0xc2, 0xdc, 0xe0, 0xb8, 0x02, 0x0c, 0x0c, 0x13, 0xc8,
+            0x1b, 0x8c, 0x2e, 0xd0, 0xaa, 0x4e, 0xb8, 0xd3, 0x0d, 0x97, 0x89, 0x36, 0x5d, 0x86,
+            0x1e, 0xc0, 0xb7, 0xfb, 0x8f, 0xc1, 0x03, 0xba, 0xd1, 0xd9, 0x8b, 0x88, 0xac, 0xc2,
+            0x06, 0x65, 0x5d, 0xe2, 0x02,
+        ];
+        let expected: &[u8] = b"The quick brown fox jumps over the lazy dog. \
+            The Quick Brown Fox Jumps Over The Lazy Dog!";
+        let mut decoder = BrotliDecoder::new();
+        assert_eq!(decoder.decompress(&compressed).unwrap(), expected);
+
+        // Truncated input must fail rather than produce partial output
+        assert!(decoder.decompress(&compressed[..40]).is_err());
+    }
 }
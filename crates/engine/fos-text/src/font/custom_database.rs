@@ -5,8 +5,9 @@ use std::sync::Arc;
 // Use local StringInterner to avoid cyclic dependency with fos-engine
 use super::intern::{StringInterner, InternedString};
 
-use super::parser::FontParser;
-use super::{FontStyle, FontWeight, FontQuery};
+use super::parser::{FontParser, NameRecord, name_ids};
+use super::{FontStyle, FontWeight, FontStretch, FontQuery};
+use super::matching::{FaceDescriptor, FontMatch, select_face, resolve_generic_family};
 use crate::{Result, TextError};
 
 /// Unique font identifier
@@ -28,6 +29,8 @@ pub struct FontEntry {
     pub style: FontStyle,
     /// Font weight
     pub weight: FontWeight,
+    /// Font stretch
+    pub stretch: FontStretch,
     /// Font data source
     pub source: FontSource,
     /// Index in font file (for TTC)
@@ -207,6 +210,7 @@ impl CustomFontDatabase {
         // Extract font info from name table
         let (family, full_name, postscript_name, style, weight) = 
             self.extract_font_info(parser)?;
+        let stretch = parser.os2().map_or(FontStretch::NORMAL, |os2| FontStretch::from_width_class(os2.width_class));
         
         let id = FontId(self.next_id);
         self.next_id += 1;
@@ -220,6 +224,7 @@ impl CustomFontDatabase {
             postscript_name,
             style,
             weight,
+            stretch,
             source,
             index,
         };
@@ -231,55 +236,104 @@ impl CustomFontDatabase {
     }
     
     /// Extract font info from parser
-    fn extract_font_info(&self, _parser: &FontParser) -> Result<(String, String, Option<String>, FontStyle, FontWeight)> {
-        // Default values - in a full implementation, parse name table
-        let family = "Unknown".to_string();
-        let full_name = "Unknown Font".to_string();
-        let postscript_name = None;
-        let style = FontStyle::Normal;
-        let weight = FontWeight::NORMAL;
-        
-        // Try to get OS/2 table for weight
-        // This is simplified - full implementation would parse name table
-        
+    fn extract_font_info(&self, parser: &FontParser) -> Result<(String, String, Option<String>, FontStyle, FontWeight)> {
+        let names = parser.names();
+        let family = NameRecord::find(&names, name_ids::TYPOGRAPHIC_FAMILY)
+            .or_else(|| NameRecord::find(&names, name_ids::FAMILY))
+            .unwrap_or("Unknown")
+            .to_string();
+        let full_name = NameRecord::find(&names, name_ids::FULL_NAME)
+            .map_or_else(|| family.clone(), str::to_string);
+        let postscript_name = NameRecord::find(&names, name_ids::POSTSCRIPT_NAME).map(str::to_string);
+
+        let subfamily = NameRecord::find(&names, name_ids::TYPOGRAPHIC_SUBFAMILY)
+            .or_else(|| NameRecord::find(&names, name_ids::SUBFAMILY))
+            .unwrap_or("")
+            .to_lowercase();
+        let style = if subfamily.contains("italic") {
+            FontStyle::Italic
+        } else if subfamily.contains("oblique") {
+            FontStyle::Oblique
+        } else {
+            FontStyle::Normal
+        };
+        let weight = parser.os2().map_or(FontWeight::NORMAL, |os2| FontWeight::from(os2.weight_class));
+
         Ok((family, full_name, postscript_name, style, weight))
     }
     
     /// Query for a matching font
     pub fn query(&self, query: &FontQuery) -> Option<FontId> {
-        // Try each family in order
-        for family_name in &query.families {
-            let family_interned = {
-                // Look up in interner - need to check if it exists
-                let mut temp_interner = StringInterner::new();
-                let _id = temp_interner.intern(family_name);
-                
-                // Search for matching family by string comparison
-                self.fonts.iter()
-                    .find(|f| self.interner.get(&f.family) == Some(family_name.as_str()))
-                    .map(|f| f.family.clone())
+        self.match_font(query).map(|m| m.id)
+    }
+    
+    /// Walk the query's families, expanding generic families to their system
+    /// families, and match the first one present; falls back to the first font
+    pub fn match_font(&self, query: &FontQuery) -> Option<FontMatch> {
+        query.families.iter()
+            .find_map(|family| {
+                self.match_face(family, query).or_else(|| {
+                    resolve_generic_family(family).iter().find_map(|generic| self.match_face(generic, query))
+                })
+            })
+            .or_else(|| {
+                let font = self.fonts.first()?;
+                Some(FontMatch::new(font.id, &Self::descriptor(font), query))
+            })
+    }
+    
+    /// Best face of a family for weight and style; family names match
+    /// case-insensitively and there is no fallback to other families
+    pub fn match_family(&self, family: &str, weight: FontWeight, style: FontStyle) -> Option<FontId> {
+        let query = FontQuery::new(&[family]).weight(weight).style(style);
+        self.match_face(family, &query).map(|m| m.id)
+    }
+    
+    /// Face of `family` the CSS font matching algorithm picks for `query`
+    pub fn match_face(&self, family: &str, query: &FontQuery) -> Option<FontMatch> {
+        let fonts: Vec<&FontEntry> = self.by_family.iter()
+            .find(|(name, _)| self.interner.get(name).is_some_and(|n| n.eq_ignore_ascii_case(family)))
+            .map(|(_, fonts)| fonts)?
+            .iter()
+            .filter_map(|id| self.font(*id))
+            .collect();
+        let faces: Vec<FaceDescriptor> = fonts.iter().map(|f| Self::descriptor(f)).collect();
+        let index = select_face(query, &faces)?;
+        Some(FontMatch::new(fonts[index].id, &faces[index], query))
+    }
+    
+    fn descriptor(font: &FontEntry) -> FaceDescriptor {
+        FaceDescriptor { weight: font.weight, stretch: font.stretch, style: font.style }
+    }
+    
+    /// Family name of a font
+    pub fn family_name(&self, id: FontId) -> Option<&str> {
+        self.interner.get(&self.font(id)?.family)
+    }
+    
+    /// Whether a font maps a character to a glyph other than .notdef
+    pub fn has_glyph(&self, id: FontId, c: char) -> bool {
+        self.with_face_data(id, |data, index| {
+            let decoded;
+            let data = if super::woff2::is_woff2(data) {
+                decoded = super::woff2::decode_woff2(data);
+                decoded.as_deref()
+            } else if super::woff::is_woff(data) {
+                decoded = super::woff::decode_woff(data);
+                decoded.as_deref()
+            } else {
+                Some(data)
             };
-            
-            if let Some(family) = family_interned {
-                if let Some(fonts) = self.by_family.get(&family) {
-                    // Find best match for weight and style
-                    let best = fonts.iter()
-                        .filter_map(|id| self.font(*id))
-                        .min_by_key(|f| {
-                            let weight_diff = (f.weight.0 as i32 - query.weight.0 as i32).abs();
-                            let style_match = if f.style == query.style { 0 } else { 1000 };
-                            weight_diff + style_match
-                        });
-                    
-                    if let Some(font) = best {
-                        return Some(font.id);
-                    }
-                }
-            }
-        }
-        
-        // Return first available font as fallback
-        self.fonts.first().map(|f| f.id)
+            data.and_then(|data| FontParser::parse_index(data, index).ok())
+                .and_then(|parser| parser.glyph_index(c))
+                .is_some_and(|glyph| glyph.0 != 0)
+        })
+        .unwrap_or(false)
+    }
+    
+    /// IDs of all fonts
+    pub fn font_ids(&self) -> impl Iterator<Item = FontId> + '_ {
+        self.fonts.iter().map(|f| f.id)
     }
     
     /// Get font by ID
@@ -1,9 +1,19 @@
 //! Emoji and Color Font Support
 //!
 //! Support for color emoji fonts (COLR, CBDT, sbix formats).
+//!
+//! [`ColorFont`] reads the color tables: COLRv0 layers with CPAL palettes,
+//! PNG strikes from CBDT/CBLC and sbix, and SVG documents. Layers are
+//! filled by the glyph rasterizer; bitmaps are decoded and scaled by the
+//! renderer, which has the image decoders.
 
 use std::collections::HashMap;
 
+use super::parser::{FontParser, FontReader};
+
+/// Palette index of a COLR layer drawn in the text color
+pub const FOREGROUND_PALETTE_INDEX: u16 = 0xFFFF;
+
 /// Color font format
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ColorFontFormat {
@@ -54,6 +64,8 @@ pub struct ColorBitmap {
     pub data: Vec<u8>,
     /// Data format
     pub format: BitmapFormat,
+    /// Pixels per em of the strike; metrics are in pixels at this size
+    pub ppem: u16,
 }
 
 /// Bitmap data format
@@ -151,6 +163,380 @@ impl EmojiRenderer {
     pub fn clear_cache(&mut self) {
         self.color_glyphs.clear();
     }
+    
+    /// Look up a color glyph of a font in the preferred format, caching it
+    pub fn load_glyph(&mut self, font: &ColorFont, glyph_id: u16, ppem: u16) -> Option<&ColorGlyph> {
+        if !self.color_glyphs.contains_key(&glyph_id) {
+            let glyph = font.color_glyph(glyph_id, ppem, self.preferred_format)?;
+            self.color_glyphs.insert(glyph_id, glyph);
+        }
+        self.color_glyphs.get(&glyph_id)
+    }
+    
+    /// Colors of COLR layers from the palette; the foreground index and
+    /// missing entries use the text color
+    pub fn layer_colors(&self, layers: &[ColorLayer], foreground: PaletteColor) -> Vec<(u16, PaletteColor)> {
+        layers.iter()
+            .map(|layer| {
+                let color = match layer.palette_index {
+                    FOREGROUND_PALETTE_INDEX => foreground,
+                    index => self.palette.get(index as usize).copied().unwrap_or(foreground),
+                };
+                (layer.glyph_id, color)
+            })
+            .collect()
+    }
+}
+
+/// Color tables of a font
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ColorFont<'a> {
+    colr: Option<&'a [u8]>,
+    cpal: Option<&'a [u8]>,
+    cblc: Option<&'a [u8]>,
+    cbdt: Option<&'a [u8]>,
+    sbix: Option<&'a [u8]>,
+    svg: Option<&'a [u8]>,
+    num_glyphs: u16,
+}
+
+impl<'a> ColorFont<'a> {
+    pub fn new(parser: &FontParser<'a>) -> Self {
+        Self {
+            colr: parser.table_data(b"COLR"),
+            cpal: parser.table_data(b"CPAL"),
+            cblc: parser.table_data(b"CBLC"),
+            cbdt: parser.table_data(b"CBDT"),
+            sbix: parser.table_data(b"sbix"),
+            svg: parser.table_data(b"SVG "),
+            num_glyphs: parser.number_of_glyphs(),
+        }
+    }
+    
+    /// Color formats the font has
+    pub fn formats(&self) -> Vec<ColorFontFormat> {
+        let mut formats = Vec::new();
+        if self.colr.is_some() {
+            formats.push(ColorFontFormat::Colr);
+        }
+        if self.cblc.is_some() && self.cbdt.is_some() {
+            formats.push(ColorFontFormat::Cbdt);
+        }
+        if self.sbix.is_some() {
+            formats.push(ColorFontFormat::Sbix);
+        }
+        if self.svg.is_some() {
+            formats.push(ColorFontFormat::Svg);
+        }
+        formats
+    }
+    
+    /// Check if the font has any color glyphs
+    pub fn has_color(&self) -> bool {
+        !self.formats().is_empty()
+    }
+    
+    /// Color glyph in the preferred format, else the first format that has
+    /// the glyph
+    pub fn color_glyph(&self, glyph_id: u16, ppem: u16, preferred: Option<ColorFontFormat>) -> Option<ColorGlyph> {
+        let mut formats = self.formats();
+        if let Some(preferred) = preferred {
+            formats.sort_by_key(|&f| f != preferred);
+        }
+        formats.into_iter().find_map(|format| match format {
+            ColorFontFormat::Colr => self.layers(glyph_id).map(ColorGlyph::Layered),
+            ColorFontFormat::Cbdt => self.cbdt_bitmap(glyph_id, ppem).map(ColorGlyph::Bitmap),
+            ColorFontFormat::Sbix => self.sbix_bitmap(glyph_id, ppem, true).map(ColorGlyph::Bitmap),
+            ColorFontFormat::Svg => self.svg_document(glyph_id).map(ColorGlyph::Svg),
+        })
+    }
+    
+    /// Palette from CPAL
+    pub fn palette(&self, index: u16) -> Option<ColorPalette> {
+        let cpal = self.cpal?;
+        let mut r = FontReader::new(cpal);
+        r.skip(2).ok()?; // version
+        let entries = r.read_u16().ok()?;
+        let num_palettes = r.read_u16().ok()?;
+        let _num_records = r.read_u16().ok()?;
+        let records_offset = r.read_u32().ok()? as usize;
+        if index >= num_palettes {
+            return None;
+        }
+        r.skip(index as usize * 2).ok()?;
+        let first = r.read_u16().ok()? as usize;
+        
+        let mut records = FontReader::new(cpal.get(records_offset + first * 4..)?);
+        let mut palette = ColorPalette::new();
+        for _ in 0..entries {
+            let b = records.read_u8().ok()?;
+            let g = records.read_u8().ok()?;
+            let r = records.read_u8().ok()?;
+            let a = records.read_u8().ok()?;
+            palette.add_color(PaletteColor::new(r, g, b, a));
+        }
+        Some(palette)
+    }
+    
+    /// COLRv0 layers of a glyph, bottom first
+    pub fn layers(&self, glyph_id: u16) -> Option<Vec<ColorLayer>> {
+        let colr = self.colr?;
+        let mut r = FontReader::new(colr);
+        r.skip(2).ok()?; // version
+        let num_base = r.read_u16().ok()? as usize;
+        let base_offset = r.read_u32().ok()? as usize;
+        let layer_offset = r.read_u32().ok()? as usize;
+        let num_layers = r.read_u16().ok()? as usize;
+        
+        // Base glyph records are sorted by glyph ID
+        let record = |i: usize| -> Option<(u16, usize, usize)> {
+            let mut r = FontReader::new(colr.get(base_offset + i * 6..)?);
+            Some((r.read_u16().ok()?, r.read_u16().ok()? as usize, r.read_u16().ok()? as usize))
+        };
+        let (mut lo, mut hi) = (0, num_base);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let (id, first, count) = record(mid)?;
+            if id == glyph_id {
+                if first + count > num_layers {
+                    return None;
+                }
+                let mut layers = FontReader::new(colr.get(layer_offset + first * 4..)?);
+                return (0..count)
+                    .map(|_| Some(ColorLayer {
+                        glyph_id: layers.read_u16().ok()?,
+                        palette_index: layers.read_u16().ok()?,
+                    }))
+                    .collect();
+            }
+            if id < glyph_id {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        None
+    }
+    
+    /// Bitmap from the best CBDT/CBLC strike for `ppem`
+    fn cbdt_bitmap(&self, glyph_id: u16, ppem: u16) -> Option<ColorBitmap> {
+        let (cblc, cbdt) = (self.cblc?, self.cbdt?);
+        let mut r = FontReader::new(cblc);
+        r.skip(4).ok()?; // version
+        let num_sizes = r.read_u32().ok()? as usize;
+        
+        let mut strikes = Vec::new();
+        for i in 0..num_sizes {
+            let offset = 8 + i * 48;
+            let mut size = FontReader::new(cblc.get(offset..)?);
+            size.skip(40).ok()?;
+            let start = size.read_u16().ok()?;
+            let end = size.read_u16().ok()?;
+            let strike_ppem = size.read_u8().ok()? as u16;
+            if (start..=end).contains(&glyph_id) {
+                strikes.push((offset, strike_ppem));
+            }
+        }
+        let (offset, strike_ppem) = best_strike(strikes, ppem)?;
+        
+        let mut size = FontReader::new(cblc.get(offset..)?);
+        let array_offset = size.read_u32().ok()? as usize;
+        let _tables_size = size.read_u32().ok()?;
+        let num_subtables = size.read_u32().ok()? as usize;
+        for j in 0..num_subtables {
+            let mut entry = FontReader::new(cblc.get(array_offset + j * 8..)?);
+            let first = entry.read_u16().ok()?;
+            let last = entry.read_u16().ok()?;
+            let subtable_offset = array_offset + entry.read_u32().ok()? as usize;
+            if !(first..=last).contains(&glyph_id) {
+                continue;
+            }
+            
+            let mut sub = FontReader::new(cblc.get(subtable_offset..)?);
+            let index_format = sub.read_u16().ok()?;
+            let image_format = sub.read_u16().ok()?;
+            let image_data_offset = sub.read_u32().ok()? as usize;
+            let index = (glyph_id - first) as usize;
+            let mut metrics = None;
+            let (start, end) = match index_format {
+                1 | 3 => {
+                    let read = |r: &mut FontReader| -> Option<usize> {
+                        Some(if index_format == 1 { r.read_u32().ok()? as usize } else { r.read_u16().ok()? as usize })
+                    };
+                    sub.skip(index * if index_format == 1 { 4 } else { 2 }).ok()?;
+                    (read(&mut sub)?, read(&mut sub)?)
+                }
+                2 => {
+                    let image_size = sub.read_u32().ok()? as usize;
+                    metrics = Some(read_big_metrics(&mut sub)?);
+                    (index * image_size, (index + 1) * image_size)
+                }
+                4 => {
+                    let num_glyphs = sub.read_u32().ok()? as usize;
+                    let mut found = None;
+                    let mut previous: Option<(u16, usize)> = None;
+                    for _ in 0..=num_glyphs {
+                        let id = sub.read_u16().ok()?;
+                        let offset = sub.read_u16().ok()? as usize;
+                        if let Some((_, prev_offset)) = previous.filter(|&(prev_id, _)| prev_id == glyph_id) {
+                            found = Some((prev_offset, offset));
+                            break;
+                        }
+                        previous = Some((id, offset));
+                    }
+                    found?
+                }
+                5 => {
+                    let image_size = sub.read_u32().ok()? as usize;
+                    metrics = Some(read_big_metrics(&mut sub)?);
+                    let num_glyphs = sub.read_u32().ok()? as usize;
+                    let position = (0..num_glyphs)
+                        .map(|_| sub.read_u16().ok())
+                        .collect::<Option<Vec<u16>>>()?
+                        .iter()
+                        .position(|&id| id == glyph_id)?;
+                    (position * image_size, (position + 1) * image_size)
+                }
+                _ => return None,
+            };
+            let mut data = FontReader::new(cbdt.get(image_data_offset + start..image_data_offset + end)?);
+            
+            // Formats 17 and 18 carry their metrics; 19 uses the index's
+            let metrics = match image_format {
+                17 => read_small_metrics(&mut data)?,
+                18 => read_big_metrics(&mut data)?,
+                19 => metrics?,
+                _ => return None,
+            };
+            let length = data.read_u32().ok()? as usize;
+            let png = data.read_bytes(length).ok()?;
+            return Some(ColorBitmap {
+                width: metrics.width,
+                height: metrics.height,
+                bearing_x: metrics.bearing_x,
+                bearing_y: metrics.bearing_y,
+                advance: metrics.advance,
+                data: png.to_vec(),
+                format: BitmapFormat::Png,
+                ppem: strike_ppem,
+            });
+        }
+        None
+    }
+    
+    /// Bitmap from the best sbix strike for `ppem`
+    fn sbix_bitmap(&self, glyph_id: u16, ppem: u16, follow_dupe: bool) -> Option<ColorBitmap> {
+        let sbix = self.sbix?;
+        if glyph_id >= self.num_glyphs {
+            return None;
+        }
+        let mut r = FontReader::new(sbix);
+        r.skip(4).ok()?; // version, flags
+        let num_strikes = r.read_u32().ok()? as usize;
+        let mut strikes = Vec::with_capacity(num_strikes);
+        for _ in 0..num_strikes {
+            let offset = r.read_u32().ok()? as usize;
+            let strike_ppem = FontReader::new(sbix.get(offset..)?).read_u16().ok()?;
+            strikes.push((offset, strike_ppem));
+        }
+        let (strike, strike_ppem) = best_strike(strikes, ppem)?;
+        
+        let mut offsets = FontReader::new(sbix.get(strike + 4 + glyph_id as usize * 4..)?);
+        let start = offsets.read_u32().ok()? as usize;
+        let end = offsets.read_u32().ok()? as usize;
+        if end <= start + 8 {
+            return None;
+        }
+        let mut data = FontReader::new(sbix.get(strike + start..strike + end)?);
+        let origin_x = data.read_i16().ok()?;
+        let origin_y = data.read_i16().ok()?;
+        let graphic_type = data.read_tag().ok()?;
+        let image = data.slice_from_here();
+        let format = match &graphic_type {
+            b"png " => BitmapFormat::Png,
+            b"jpg " => BitmapFormat::Jpeg,
+            b"dupe" if follow_dupe => {
+                let original = FontReader::new(image).read_u16().ok()?;
+                return self.sbix_bitmap(original, ppem, false);
+            }
+            _ => return None,
+        };
+        let (width, height) = png_size(image).unwrap_or((0, 0));
+        Some(ColorBitmap {
+            width,
+            height,
+            bearing_x: origin_x,
+            bearing_y: origin_y.saturating_add(height as i16),
+            advance: 0,
+            data: image.to_vec(),
+            format,
+            ppem: strike_ppem,
+        })
+    }
+    
+    /// SVG document holding a glyph; gzip-compressed documents are skipped
+    pub fn svg_document(&self, glyph_id: u16) -> Option<String> {
+        let svg = self.svg?;
+        let mut r = FontReader::new(svg);
+        r.skip(2).ok()?; // version
+        let list_offset = r.read_u32().ok()? as usize;
+        let mut list = FontReader::new(svg.get(list_offset..)?);
+        let count = list.read_u16().ok()?;
+        for _ in 0..count {
+            let start = list.read_u16().ok()?;
+            let end = list.read_u16().ok()?;
+            let offset = list_offset + list.read_u32().ok()? as usize;
+            let length = list.read_u32().ok()? as usize;
+            if (start..=end).contains(&glyph_id) {
+                let document = svg.get(offset..offset + length)?;
+                if document.starts_with(&[0x1F, 0x8B]) {
+                    return None;
+                }
+                return String::from_utf8(document.to_vec()).ok();
+            }
+        }
+        None
+    }
+}
+
+/// Bitmap glyph metrics, in pixels of the strike
+struct BitmapMetrics {
+    width: u16,
+    height: u16,
+    bearing_x: i16,
+    bearing_y: i16,
+    advance: u16,
+}
+
+fn read_small_metrics(r: &mut FontReader) -> Option<BitmapMetrics> {
+    let height = r.read_u8().ok()? as u16;
+    let width = r.read_u8().ok()? as u16;
+    let bearing_x = r.read_u8().ok()? as i8 as i16;
+    let bearing_y = r.read_u8().ok()? as i8 as i16;
+    let advance = r.read_u8().ok()? as u16;
+    Some(BitmapMetrics { width, height, bearing_x, bearing_y, advance })
+}
+
+fn read_big_metrics(r: &mut FontReader) -> Option<BitmapMetrics> {
+    let metrics = read_small_metrics(r)?;
+    r.skip(3).ok()?; // vertical metrics
+    Some(metrics)
+}
+
+/// Smallest strike at least `ppem` large, else the largest one
+fn best_strike<T>(strikes: Vec<(T, u16)>, ppem: u16) -> Option<(T, u16)> {
+    strikes.into_iter().min_by_key(|&(_, size)| {
+        if size >= ppem { (0, size) } else { (1, u16::MAX - size) }
+    })
+}
+
+/// Width and height from a PNG header
+fn png_size(data: &[u8]) -> Option<(u16, u16)> {
+    if !data.starts_with(b"\x89PNG") {
+        return None;
+    }
+    let mut r = FontReader::new(data.get(16..24)?);
+    Some((r.read_u32().ok()? as u16, r.read_u32().ok()? as u16))
 }
 
 /// Check if character is an emoji
@@ -238,6 +624,16 @@ pub fn is_zwj(c: char) -> bool {
     c == '\u{200D}'
 }
 
+/// Check if character is a regional indicator; pairs form flags
+pub fn is_regional_indicator(c: char) -> bool {
+    matches!(c, '\u{1F1E6}'..='\u{1F1FF}')
+}
+
+/// Check if character is a tag of an emoji tag sequence (subdivision flags)
+pub fn is_emoji_tag(c: char) -> bool {
+    matches!(c, '\u{E0020}'..='\u{E007F}')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,4 +673,70 @@ mod tests {
         assert!(renderer.has_color_glyph(100));
         assert!(!renderer.has_color_glyph(200));
     }
+    
+    fn be16(out: &mut Vec<u8>, values: &[u16]) {
+        for v in values {
+            out.extend_from_slice(&v.to_be_bytes());
+        }
+    }
+    
+    #[test]
+    fn test_colr_cpal() {
+        // Glyph 5 = glyph 1 in palette entry 1 over glyph 2 in the text color
+        let mut colr = Vec::new();
+        be16(&mut colr, &[0, 1, 0, 14, 0, 20, 2]);
+        be16(&mut colr, &[5, 0, 2]);
+        be16(&mut colr, &[1, 1, 2, FOREGROUND_PALETTE_INDEX]);
+        let mut cpal = Vec::new();
+        be16(&mut cpal, &[0, 2, 1, 2, 0, 14, 0]);
+        cpal.extend_from_slice(&[0, 0, 255, 255, 0, 255, 0, 128]); // BGRA
+        
+        let font = ColorFont { colr: Some(&colr), cpal: Some(&cpal), ..Default::default() };
+        assert_eq!(font.formats(), [ColorFontFormat::Colr]);
+        assert!(font.layers(4).is_none());
+        let Some(ColorGlyph::Layered(layers)) = font.color_glyph(5, 16, None) else {
+            panic!("no layers");
+        };
+        
+        let mut renderer = EmojiRenderer::new();
+        renderer.set_palette(font.palette(0).unwrap());
+        let black = PaletteColor::new(0, 0, 0, 255);
+        let colors: Vec<(u16, (u8, u8, u8, u8))> = renderer.layer_colors(&layers, black)
+            .into_iter()
+            .map(|(glyph, color)| (glyph, color.to_rgba()))
+            .collect();
+        assert_eq!(colors, [(1, (0, 255, 0, 128)), (2, (0, 0, 0, 255))]);
+    }
+    
+    #[test]
+    fn test_sbix_strikes() {
+        // Two strikes (20 and 40 ppem) for two glyphs; glyph 1 duplicates glyph 0
+        let png: Vec<u8> = [b"\x89PNG\r\n\x1a\n".as_slice(), &[0, 0, 0, 13], b"IHDR", &[0, 0, 0, 20, 0, 0, 0, 18]].concat();
+        let mut sbix = Vec::new();
+        be16(&mut sbix, &[1, 1, 0, 2, 0, 16]);
+        sbix.extend_from_slice(&(16 + 16 + png.len() as u32 + 8 + 10).to_be_bytes());
+        for (strike_ppem, glyph0) in [(20u16, png.clone()), (40, png.clone())] {
+            let start = 16u32;
+            let end0 = start + 8 + glyph0.len() as u32;
+            be16(&mut sbix, &[strike_ppem, 72]);
+            for offset in [start, end0, end0 + 10] {
+                sbix.extend_from_slice(&offset.to_be_bytes());
+            }
+            be16(&mut sbix, &[2, (-3i16) as u16]);
+            sbix.extend_from_slice(b"png ");
+            sbix.extend_from_slice(&glyph0);
+            be16(&mut sbix, &[0, 0]);
+            sbix.extend_from_slice(b"dupe");
+            be16(&mut sbix, &[0]);
+        }
+        
+        let font = ColorFont { sbix: Some(&sbix), num_glyphs: 2, ..Default::default() };
+        let Some(ColorGlyph::Bitmap(bitmap)) = font.color_glyph(1, 30, None) else {
+            panic!("no bitmap");
+        };
+        assert_eq!((bitmap.ppem, bitmap.width, bitmap.height), (40, 20, 18));
+        assert_eq!((bitmap.bearing_x, bitmap.bearing_y), (2, 15));
+        assert_eq!(font.color_glyph(0, 12, None).map(|g| matches!(g, ColorGlyph::Bitmap(b) if b.ppem == 20)), Some(true));
+        assert!(font.color_glyph(2, 12, None).is_none());
+    }
 }
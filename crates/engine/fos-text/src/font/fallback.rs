@@ -0,0 +1,225 @@
+//! Font Fallback
+//!
+//! Splits text into runs that one font can render, so missing glyphs come
+//! from another font instead of drawing .notdef boxes. Each cluster tries
+//! the `font-family` list, then the system fallbacks for its script, then
+//! any installed font covering it. Decisions are cached per character and
+//! family list.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use super::emoji::{is_emoji, is_skin_tone_modifier, is_zwj};
+use super::{resolve_generic_family, FontDatabase, FontId, FontQuery};
+use crate::shaping::{is_cluster_extend, Script};
+
+/// Text run rendered with a single font
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontRun {
+    /// Start byte offset
+    pub start: usize,
+    /// End byte offset (exclusive)
+    pub end: usize,
+    /// Font for the run; `None` if no installed font covers it
+    pub font: Option<FontId>,
+    /// Script of the run
+    pub script: Script,
+    /// Emoji presentation
+    pub emoji: bool,
+}
+
+/// Font fallback with cached decisions
+#[derive(Debug, Default)]
+pub struct FontFallback {
+    /// Font for a character, family list and script
+    decisions: HashMap<(char, u64), Option<FontId>>,
+    /// Whether a font covers a character
+    coverage: HashMap<(FontId, char), bool>,
+    /// Database size the caches were built against
+    db_len: usize,
+}
+
+impl FontFallback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Split text into font runs for a query
+    pub fn itemize(&mut self, db: &FontDatabase, text: &str, query: &FontQuery) -> Vec<FontRun> {
+        let mut runs: Vec<FontRun> = Vec::new();
+        for (start, end) in clusters(text) {
+            let cluster = &text[start..end];
+            let base = cluster.chars().next().unwrap_or(' ');
+            let emoji = (is_emoji(base) || cluster.contains('\u{FE0F}')) && !cluster.contains('\u{FE0E}');
+            let mut script = Script::of(base);
+
+            let neutral = matches!(script, Script::Common | Script::Inherited) && !emoji;
+            // Spaces and punctuation continue the current run when its font
+            // covers them
+            if let Some(last) = runs.last_mut().filter(|last| neutral && !last.emoji) {
+                let covered = match last.font {
+                    Some(font) => self.covers(db, font, base),
+                    None => true,
+                };
+                if covered {
+                    last.end = end;
+                    continue;
+                }
+                script = last.script;
+            }
+
+            let font = self.font_for_char(db, base, query, script, emoji);
+            match runs.last_mut() {
+                Some(last) if last.font == font && last.emoji == emoji && last.script == script => last.end = end,
+                _ => runs.push(FontRun { start, end, font, script, emoji }),
+            }
+        }
+        runs
+    }
+
+    /// Font for one character: the family list, then system fallbacks for
+    /// the script, then any font with coverage
+    pub fn font_for_char(
+        &mut self,
+        db: &FontDatabase,
+        c: char,
+        query: &FontQuery,
+        script: Script,
+        emoji: bool,
+    ) -> Option<FontId> {
+        if self.db_len != db.len() {
+            self.clear();
+            self.db_len = db.len();
+        }
+        let key = (c, query_key(query, script, emoji));
+        if let Some(&font) = self.decisions.get(&key) {
+            return font;
+        }
+
+        let mut families: Vec<&str> = Vec::new();
+        if emoji {
+            families.extend(fallback_families(script, true));
+        }
+        for family in &query.families {
+            match resolve_generic_family(family) {
+                [] => families.push(family),
+                generic => families.extend(generic),
+            }
+        }
+        families.extend(fallback_families(script, false));
+
+        let font = families
+            .iter()
+            .filter_map(|family| db.match_face(family, query).map(|m| m.id))
+            .chain(db.font_ids())
+            .find(|&font| self.covers(db, font, c));
+        self.decisions.insert(key, font);
+        font
+    }
+
+    /// Number of cached decisions
+    pub fn len(&self) -> usize {
+        self.decisions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decisions.is_empty()
+    }
+
+    /// Drop cached decisions, e.g. after loading web fonts
+    pub fn clear(&mut self) {
+        self.decisions.clear();
+        self.coverage.clear();
+    }
+
+    fn covers(&mut self, db: &FontDatabase, font: FontId, c: char) -> bool {
+        *self.coverage.entry((font, c)).or_insert_with(|| db.has_glyph(font, c))
+    }
+}
+
+/// System font families to try for a script
+pub fn fallback_families(script: Script, emoji: bool) -> &'static [&'static str] {
+    if emoji {
+        return &["Noto Color Emoji", "Apple Color Emoji", "Segoe UI Emoji", "Twemoji Mozilla", "Symbola"];
+    }
+    match script {
+        Script::Han | Script::Hiragana | Script::Katakana | Script::Bopomofo => &[
+            "Noto Sans CJK JP", "Noto Sans CJK SC", "Source Han Sans", "PingFang SC",
+            "Hiragino Sans", "Microsoft YaHei", "Yu Gothic",
+        ],
+        Script::Hangul => &["Noto Sans CJK KR", "Apple SD Gothic Neo", "Malgun Gothic"],
+        Script::Arabic => &["Noto Naskh Arabic", "Noto Sans Arabic", "Geeza Pro", "Segoe UI"],
+        Script::Hebrew => &["Noto Sans Hebrew", "Arial Hebrew", "Segoe UI"],
+        Script::Devanagari => &["Noto Sans Devanagari", "Kohinoor Devanagari", "Nirmala UI", "Mangal"],
+        Script::Bengali => &["Noto Sans Bengali", "Kohinoor Bangla", "Nirmala UI"],
+        Script::Tamil => &["Noto Sans Tamil", "Tamil Sangam MN", "Nirmala UI"],
+        Script::Thai => &["Noto Sans Thai", "Thonburi", "Leelawadee UI"],
+        Script::Armenian => &["Noto Sans Armenian", "DejaVu Sans"],
+        Script::Georgian => &["Noto Sans Georgian", "DejaVu Sans"],
+        Script::Ethiopic => &["Noto Sans Ethiopic", "Kefa", "Nyala"],
+        _ => &["Noto Sans", "DejaVu Sans", "Arial Unicode MS", "Segoe UI Symbol"],
+    }
+}
+
+/// Byte ranges of clusters: a base character with its combining marks,
+/// variation selectors, skin tones and ZWJ sequences
+fn clusters(text: &str) -> Vec<(usize, usize)> {
+    let mut clusters: Vec<(usize, usize)> = Vec::new();
+    let mut after_zwj = false;
+    for (i, c) in text.char_indices() {
+        let end = i + c.len_utf8();
+        let extends = after_zwj || is_cluster_extend(c) || is_skin_tone_modifier(c);
+        match clusters.last_mut() {
+            Some(last) if extends => last.1 = end,
+            _ => clusters.push((i, end)),
+        }
+        after_zwj = is_zwj(c);
+    }
+    clusters
+}
+
+fn query_key(query: &FontQuery, script: Script, emoji: bool) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    query.families.hash(&mut hasher);
+    query.weight.hash(&mut hasher);
+    query.style.hash(&mut hasher);
+    query.stretch.0.to_bits().hash(&mut hasher);
+    script.hash(&mut hasher);
+    emoji.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clusters() {
+        // e + combining acute, family emoji joined by ZWJ, thumbs up + skin tone
+        let text = "e\u{301}\u{1F468}\u{200D}\u{1F469}\u{1F44D}\u{1F3FD}a";
+        let spans: Vec<&str> = clusters(text).into_iter().map(|(s, e)| &text[s..e]).collect();
+        assert_eq!(spans, ["e\u{301}", "\u{1F468}\u{200D}\u{1F469}", "\u{1F44D}\u{1F3FD}", "a"]);
+    }
+
+    #[test]
+    fn test_itemize_coverage() {
+        let path = std::path::Path::new("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf");
+        if !path.exists() {
+            return;
+        }
+        let mut db = FontDatabase::new();
+        let dejavu = db.load_font_file(path).unwrap()[0];
+        assert_eq!(db.family_name(dejavu), Some("DejaVu Sans"));
+
+        let mut fallback = FontFallback::new();
+        let query = FontQuery::new(&["Missing Family", "sans-serif"]);
+        let runs = fallback.itemize(&db, "Hi, 日本 ok", &query);
+        assert_eq!(runs.len(), 3);
+        assert_eq!((runs[0].start, runs[0].end, runs[0].font), (0, 4, Some(dejavu)));
+        assert_eq!((runs[1].script, runs[1].font), (Script::Han, None));
+        // The space stays with the preceding run
+        assert_eq!(&"Hi, 日本 ok"[runs[2].start..], "ok");
+        assert!(!fallback.is_empty());
+    }
+}
@@ -1,6 +1,9 @@
 //! Font matching and query
+//!
+//! Face selection follows the CSS font matching algorithm: within a family,
+//! candidates are narrowed by stretch, then style, then weight.
 
-use super::{FontWeight, FontStyle};
+use super::{FontId, FontWeight, FontStretch, FontStyle};
 
 /// Font query for matching
 #[derive(Debug, Clone)]
@@ -11,6 +14,10 @@ pub struct FontQuery {
     pub weight: FontWeight,
     /// Desired style
     pub style: FontStyle,
+    /// Desired stretch
+    pub stretch: FontStretch,
+    /// Whether missing bold/oblique faces may be synthesized
+    pub synthesis: FontSynthesis,
 }
 
 impl FontQuery {
@@ -20,6 +27,8 @@ impl FontQuery {
             families: families.iter().map(|s| s.to_string()).collect(),
             weight: FontWeight::NORMAL,
             style: FontStyle::Normal,
+            stretch: FontStretch::NORMAL,
+            synthesis: FontSynthesis::default(),
         }
     }
     
@@ -35,6 +44,18 @@ impl FontQuery {
         self
     }
     
+    /// Set font stretch
+    pub fn stretch(mut self, stretch: FontStretch) -> Self {
+        self.stretch = stretch;
+        self
+    }
+    
+    /// Set which faces may be synthesized
+    pub fn synthesis(mut self, synthesis: FontSynthesis) -> Self {
+        self.synthesis = synthesis;
+        self
+    }
+    
     /// Set bold weight
     pub fn bold(self) -> Self {
         self.weight(FontWeight::BOLD)
@@ -52,6 +73,117 @@ impl Default for FontQuery {
     }
 }
 
+/// Synthesis allowed when a family lacks a face (CSS `font-synthesis`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontSynthesis {
+    /// Embolden lighter faces for weights of 600 and above
+    pub weight: bool,
+    /// Slant upright faces for italic and oblique
+    pub style: bool,
+}
+
+impl FontSynthesis {
+    pub const NONE: FontSynthesis = FontSynthesis { weight: false, style: false };
+}
+
+impl Default for FontSynthesis {
+    fn default() -> Self {
+        Self { weight: true, style: true }
+    }
+}
+
+/// Properties of an available face
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaceDescriptor {
+    pub weight: FontWeight,
+    pub stretch: FontStretch,
+    pub style: FontStyle,
+}
+
+/// Face chosen for a query and the synthesis it needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontMatch {
+    pub id: FontId,
+    /// Draw the face emboldened
+    pub synthetic_bold: bool,
+    /// Draw the face slanted
+    pub synthetic_oblique: bool,
+}
+
+impl FontMatch {
+    /// Match of `face` for `query`, synthesizing what the face lacks
+    pub fn new(id: FontId, face: &FaceDescriptor, query: &FontQuery) -> Self {
+        Self {
+            id,
+            synthetic_bold: query.synthesis.weight && query.weight.0 >= 600 && face.weight.0 < 600,
+            synthetic_oblique: query.synthesis.style && query.style != FontStyle::Normal && face.style == FontStyle::Normal,
+        }
+    }
+}
+
+/// Index of the face the CSS font matching algorithm picks for `query`
+pub fn select_face(query: &FontQuery, faces: &[FaceDescriptor]) -> Option<usize> {
+    let mut candidates: Vec<usize> = (0..faces.len()).collect();
+    
+    let stretch = nearest_stretch(query.stretch.0, candidates.iter().map(|&i| faces[i].stretch.0))?;
+    candidates.retain(|&i| faces[i].stretch.0 == stretch);
+    
+    let style = style_order(query.style)
+        .into_iter()
+        .find(|&style| candidates.iter().any(|&i| faces[i].style == style))?;
+    candidates.retain(|&i| faces[i].style == style);
+    
+    let weight = nearest_weight(query.weight.0, candidates.iter().map(|&i| faces[i].weight.0))?;
+    candidates.into_iter().find(|&i| faces[i].weight.0 == weight)
+}
+
+/// Condensed requests prefer narrower faces, expanded requests wider ones
+fn nearest_stretch(desired: f32, available: impl Iterator<Item = f32>) -> Option<f32> {
+    let (mut narrower, mut wider): (Option<f32>, Option<f32>) = (None, None);
+    for stretch in available {
+        if stretch == desired {
+            return Some(stretch);
+        } else if stretch < desired {
+            narrower = Some(narrower.map_or(stretch, |n| n.max(stretch)));
+        } else {
+            wider = Some(wider.map_or(stretch, |w| w.min(stretch)));
+        }
+    }
+    if desired <= 100.0 { narrower.or(wider) } else { wider.or(narrower) }
+}
+
+/// Styles to try, most preferred first
+fn style_order(style: FontStyle) -> [FontStyle; 3] {
+    match style {
+        FontStyle::Italic => [FontStyle::Italic, FontStyle::Oblique, FontStyle::Normal],
+        FontStyle::Oblique => [FontStyle::Oblique, FontStyle::Italic, FontStyle::Normal],
+        FontStyle::Normal => [FontStyle::Normal, FontStyle::Oblique, FontStyle::Italic],
+    }
+}
+
+/// Weights between 400 and 500 try heavier faces up to 500 first, then
+/// lighter faces, then heavier ones; lighter requests look down first and
+/// bolder requests look up first
+fn nearest_weight(desired: u16, available: impl Iterator<Item = u16>) -> Option<u16> {
+    let (mut lighter, mut heavier): (Option<u16>, Option<u16>) = (None, None);
+    for weight in available {
+        if weight == desired {
+            return Some(weight);
+        } else if weight < desired {
+            lighter = lighter.max(Some(weight));
+        } else {
+            heavier = Some(heavier.map_or(weight, |h| h.min(weight)));
+        }
+    }
+    if (400..=500).contains(&desired) {
+        heavier.filter(|&w| w <= 500).or(lighter).or(heavier)
+    } else if desired < 400 {
+        lighter.or(heavier)
+    } else {
+        heavier.or(lighter)
+    }
+}
+
 /// Resolve generic font family to system families
 pub fn resolve_generic_family(family: &str) -> &[&str] {
     match family.to_lowercase().as_str() {
@@ -67,3 +199,64 @@ pub fn resolve_generic_family(family: &str) -> &[&str] {
         _ => &[],
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn face(weight: u16, stretch: FontStretch, style: FontStyle) -> FaceDescriptor {
+        FaceDescriptor { weight: FontWeight(weight), stretch, style }
+    }
+    
+    #[test]
+    fn test_weight_matching() {
+        let faces = [
+            face(300, FontStretch::NORMAL, FontStyle::Normal),
+            face(600, FontStretch::NORMAL, FontStyle::Normal),
+            face(900, FontStretch::NORMAL, FontStyle::Normal),
+        ];
+        let pick = |weight| faces[select_face(&FontQuery::default().weight(FontWeight(weight)), &faces).unwrap()].weight.0;
+        // 400 and 500 look below before going past 500
+        assert_eq!(pick(400), 300);
+        assert_eq!(pick(500), 300);
+        assert_eq!(pick(200), 300);
+        assert_eq!(pick(350), 300);
+        assert_eq!(pick(700), 900);
+        
+        let faces = [face(300, FontStretch::NORMAL, FontStyle::Normal), face(500, FontStretch::NORMAL, FontStyle::Normal)];
+        assert_eq!(select_face(&FontQuery::default(), &faces), Some(1));
+        assert_eq!(select_face(&FontQuery::default(), &[]), None);
+    }
+    
+    #[test]
+    fn test_stretch_before_style() {
+        let faces = [
+            face(400, FontStretch::CONDENSED, FontStyle::Italic),
+            face(400, FontStretch::NORMAL, FontStyle::Normal),
+            face(400, FontStretch::EXPANDED, FontStyle::Oblique),
+        ];
+        let italic = FontQuery::default().italic();
+        assert_eq!(select_face(&italic, &faces), Some(1));
+        assert_eq!(select_face(&italic.clone().stretch(FontStretch::SEMI_CONDENSED), &faces), Some(0));
+        assert_eq!(select_face(&italic.stretch(FontStretch::SEMI_EXPANDED), &faces), Some(2));
+        
+        // Normal style prefers oblique over italic
+        let faces = [face(400, FontStretch::NORMAL, FontStyle::Italic), face(400, FontStretch::NORMAL, FontStyle::Oblique)];
+        assert_eq!(select_face(&FontQuery::default(), &faces), Some(1));
+    }
+    
+    #[test]
+    fn test_synthesis() {
+        let regular = face(400, FontStretch::NORMAL, FontStyle::Normal);
+        let query = FontQuery::default().bold().italic();
+        let m = FontMatch::new(FontId(0), &regular, &query);
+        assert!(m.synthetic_bold && m.synthetic_oblique);
+        
+        let m = FontMatch::new(FontId(0), &regular, &query.synthesis(FontSynthesis { weight: false, style: true }));
+        assert!(!m.synthetic_bold && m.synthetic_oblique);
+        
+        let bold_italic = face(700, FontStretch::NORMAL, FontStyle::Italic);
+        let m = FontMatch::new(FontId(0), &bold_italic, &FontQuery::default().bold().italic());
+        assert!(!m.synthetic_bold && !m.synthetic_oblique);
+    }
+}
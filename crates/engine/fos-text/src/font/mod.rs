@@ -20,6 +20,8 @@ pub mod arena;
 pub mod fixed_point;
 // Custom database (replaces fontdb)
 mod custom_database;
+// Per-script font fallback
+pub mod fallback;
 
 // Original modules
 mod face;
@@ -31,14 +33,15 @@ pub mod optimization;
 // Re-export from custom implementations
 pub use custom_database::{CustomFontDatabase as FontDatabase, FontId, FontEntry, FontSource};
 pub use face::FontFace;
-pub use matching::{FontQuery, resolve_generic_family};
-pub use variable::{FontAxis, VariableFont, VariableFontInstance, NamedInstance, axis_tags};
-pub use emoji::{EmojiRenderer, ColorGlyph, ColorFontFormat, is_emoji};
+pub use fallback::{FontFallback, FontRun, fallback_families};
+pub use matching::{FontQuery, FontSynthesis, FontMatch, FaceDescriptor, select_face, resolve_generic_family};
+pub use variable::{FontAxis, VariableFont, VariableFontInstance, NamedInstance, FontVariationStyle, axis_tags, parse_font_variation_settings};
+pub use emoji::{EmojiRenderer, ColorFont, ColorGlyph, ColorFontFormat, ColorLayer, PaletteColor, is_emoji};
 pub use optimization::{FontSubsetter, GlyphStreamer, SharedFontCache, MmapFont, GlyphMetricsCache};
 pub use parser::{GlyphId, OutlineBuilder, BoundingBox, FontParser};
 
 /// Font weight (100-900)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct FontWeight(pub u16);
 
 impl FontWeight {
@@ -60,10 +63,47 @@ impl From<u16> for FontWeight {
 }
 
 /// Font style
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum FontStyle {
     #[default]
     Normal,
     Italic,
     Oblique,
 }
+
+/// Font stretch as a percentage of normal width (50-200)
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct FontStretch(pub f32);
+
+impl FontStretch {
+    pub const ULTRA_CONDENSED: FontStretch = FontStretch(50.0);
+    pub const EXTRA_CONDENSED: FontStretch = FontStretch(62.5);
+    pub const CONDENSED: FontStretch = FontStretch(75.0);
+    pub const SEMI_CONDENSED: FontStretch = FontStretch(87.5);
+    pub const NORMAL: FontStretch = FontStretch(100.0);
+    pub const SEMI_EXPANDED: FontStretch = FontStretch(112.5);
+    pub const EXPANDED: FontStretch = FontStretch(125.0);
+    pub const EXTRA_EXPANDED: FontStretch = FontStretch(150.0);
+    pub const ULTRA_EXPANDED: FontStretch = FontStretch(200.0);
+
+    /// Stretch for an OS/2 usWidthClass (1-9)
+    pub fn from_width_class(class: u16) -> Self {
+        match class {
+            1 => Self::ULTRA_CONDENSED,
+            2 => Self::EXTRA_CONDENSED,
+            3 => Self::CONDENSED,
+            4 => Self::SEMI_CONDENSED,
+            6 => Self::SEMI_EXPANDED,
+            7 => Self::EXPANDED,
+            8 => Self::EXTRA_EXPANDED,
+            9 => Self::ULTRA_EXPANDED,
+            _ => Self::NORMAL,
+        }
+    }
+}
+
+impl Default for FontStretch {
+    fn default() -> Self {
+        Self::NORMAL
+    }
+}
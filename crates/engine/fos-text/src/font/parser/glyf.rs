@@ -2,6 +2,7 @@
 
 use super::reader::FontReader;
 use super::outline::OutlineBuilder;
+use super::variations::GlyphVariations;
 
 /// Get glyph offset from loca table
 pub fn get_glyph_offset(loca_data: &[u8], glyph_index: u16, index_format: u16) -> Option<u32> {
@@ -18,12 +19,13 @@ pub fn get_glyph_offset(loca_data: &[u8], glyph_index: u16, index_format: u16) -
     }
 }
 
-/// Outline a glyph
+/// Outline a glyph, applying `gvar` deltas if given
 pub fn outline_glyph<B: OutlineBuilder>(
     glyf_data: &[u8],
     loca_data: &[u8],
     glyph_index: u16,
     index_format: u16,
+    variations: Option<&GlyphVariations>,
     builder: &mut B,
 ) -> Option<()> {
     let offset = get_glyph_offset(loca_data, glyph_index, index_format)?;
@@ -43,9 +45,9 @@ pub fn outline_glyph<B: OutlineBuilder>(
     let _y_max = reader.read_i16().ok()?;
     
     if num_contours >= 0 {
-        outline_simple_glyph(glyph_data, num_contours as u16, builder)
+        outline_simple_glyph(glyph_data, num_contours as u16, glyph_index, variations, builder)
     } else {
-        outline_compound_glyph(glyf_data, loca_data, glyph_data, index_format, builder)
+        outline_compound_glyph(glyf_data, loca_data, glyph_data, glyph_index, index_format, variations, builder)
     }
 }
 
@@ -53,6 +55,8 @@ pub fn outline_glyph<B: OutlineBuilder>(
 fn outline_simple_glyph<B: OutlineBuilder>(
     glyph_data: &[u8],
     num_contours: u16,
+    glyph_index: u16,
+    variations: Option<&GlyphVariations>,
     builder: &mut B,
 ) -> Option<()> {
     if num_contours == 0 {
@@ -90,7 +94,7 @@ fn outline_simple_glyph<B: OutlineBuilder>(
     }
     
     // Read x coordinates
-    let mut x_coords = Vec::with_capacity(num_points);
+    let mut x_coords: Vec<f32> = Vec::with_capacity(num_points);
     let mut x = 0i32;
     for &flag in &flags {
         let is_short = flag & 0x02 != 0;
@@ -104,11 +108,11 @@ fn outline_simple_glyph<B: OutlineBuilder>(
         }
         // else: same as previous (x unchanged)
         
-        x_coords.push(x);
+        x_coords.push(x as f32);
     }
     
     // Read y coordinates
-    let mut y_coords = Vec::with_capacity(num_points);
+    let mut y_coords: Vec<f32> = Vec::with_capacity(num_points);
     let mut y = 0i32;
     for &flag in &flags {
         let is_short = flag & 0x04 != 0;
@@ -121,7 +125,23 @@ fn outline_simple_glyph<B: OutlineBuilder>(
             y += reader.read_i16().ok()? as i32;
         }
         
-        y_coords.push(y);
+        y_coords.push(y as f32);
+    }
+    
+    // Move the points by the glyph's variation deltas
+    if let Some(variations) = variations {
+        // Phantom points only carry advance deltas, their position is unused
+        let points: Vec<(f32, f32)> = x_coords.iter().zip(&y_coords)
+            .map(|(&x, &y)| (x, y))
+            .chain([(0.0, 0.0); 4])
+            .collect();
+        let ends: Vec<usize> = end_points.iter().map(|&e| e as usize).collect();
+        if let Some(deltas) = variations.glyph_deltas(glyph_index, &points, &ends) {
+            for (i, (dx, dy)) in deltas.into_iter().take(num_points).enumerate() {
+                x_coords[i] += dx;
+                y_coords[i] += dy;
+            }
+        }
     }
     
     // Build outline
@@ -146,8 +166,8 @@ fn outline_simple_glyph<B: OutlineBuilder>(
             }
         }
         
-        let x0 = x_coords[first_on] as f32;
-        let y0 = y_coords[first_on] as f32;
+        let x0 = x_coords[first_on];
+        let y0 = y_coords[first_on];
         builder.move_to(x0, y0);
         
         let mut i = (first_on - contour_start + 1) % contour_len;
@@ -160,8 +180,8 @@ fn outline_simple_glyph<B: OutlineBuilder>(
         for _ in 0..contour_len {
             let idx = contour_start + i;
             let on_curve = flags[idx] & 0x01 != 0;
-            let x = x_coords[idx] as f32;
-            let y = y_coords[idx] as f32;
+            let x = x_coords[idx];
+            let y = y_coords[idx];
             
             if on_curve {
                 if prev_on_curve {
@@ -205,7 +225,9 @@ fn outline_compound_glyph<B: OutlineBuilder>(
     glyf_data: &[u8],
     loca_data: &[u8],
     glyph_data: &[u8],
+    glyph_index: u16,
     index_format: u16,
+    variations: Option<&GlyphVariations>,
     builder: &mut B,
 ) -> Option<()> {
     let mut reader = FontReader::new(glyph_data);
@@ -218,45 +240,109 @@ fn outline_compound_glyph<B: OutlineBuilder>(
     const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
     const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
     
+    // (glyph, offset given, [xx, yx, xy, yy, dx, dy])
+    let mut components = Vec::new();
     let mut more = true;
     while more {
         let flags = reader.read_u16().ok()?;
-        let glyph_index = reader.read_u16().ok()?;
+        let component = reader.read_u16().ok()?;
         
         // Read offset
-        let (_dx, _dy) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
-            let arg1 = reader.read_i16().ok()? as f32;
-            let arg2 = reader.read_i16().ok()? as f32;
-            if flags & ARGS_ARE_XY_VALUES != 0 {
-                (arg1, arg2)
-            } else {
-                (0.0, 0.0) // Point matching - not fully supported
-            }
+        let (arg1, arg2) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            (reader.read_i16().ok()? as f32, reader.read_i16().ok()? as f32)
         } else {
-            let arg1 = reader.read_u8().ok()? as i8 as f32;
-            let arg2 = reader.read_u8().ok()? as i8 as f32;
-            if flags & ARGS_ARE_XY_VALUES != 0 {
-                (arg1, arg2)
-            } else {
-                (0.0, 0.0)
-            }
+            (reader.read_u8().ok()? as i8 as f32, reader.read_u8().ok()? as i8 as f32)
         };
+        // Point matching is not supported; such components are not moved
+        let is_offset = flags & ARGS_ARE_XY_VALUES != 0;
+        let (dx, dy) = if is_offset { (arg1, arg2) } else { (0.0, 0.0) };
         
-        // Read transform (simplified - just offset for now)
-        if flags & WE_HAVE_A_SCALE != 0 {
-            reader.skip(2).ok()?;
+        let (xx, yx, xy, yy) = if flags & WE_HAVE_A_SCALE != 0 {
+            let scale = read_f2dot14(&mut reader)?;
+            (scale, 0.0, 0.0, scale)
         } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
-            reader.skip(4).ok()?;
+            (read_f2dot14(&mut reader)?, 0.0, 0.0, read_f2dot14(&mut reader)?)
         } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
-            reader.skip(8).ok()?;
-        }
-        
-        // Recursively outline component glyph
-        // Note: For proper implementation, we'd need transform support
-        outline_glyph(glyf_data, loca_data, glyph_index, index_format, builder)?;
+            (
+                read_f2dot14(&mut reader)?,
+                read_f2dot14(&mut reader)?,
+                read_f2dot14(&mut reader)?,
+                read_f2dot14(&mut reader)?,
+            )
+        } else {
+            (1.0, 0.0, 0.0, 1.0)
+        };
+        components.push((component, is_offset, [xx, yx, xy, yy, dx, dy]));
         
         more = flags & MORE_COMPONENTS != 0;
     }
     
+    // In gvar each component offset is one point
+    if let Some(variations) = variations {
+        let points: Vec<(f32, f32)> = components.iter()
+            .map(|(_, _, m)| (m[4], m[5]))
+            .chain([(0.0, 0.0); 4])
+            .collect();
+        if let Some(deltas) = variations.glyph_deltas(glyph_index, &points, &[]) {
+            for ((_, is_offset, m), (dx, dy)) in components.iter_mut().zip(deltas) {
+                if *is_offset {
+                    m[4] += dx;
+                    m[5] += dy;
+                }
+            }
+        }
+    }
+    
+    for (component, _, matrix) in components {
+        let mut transformed = Transformed { inner: &mut *builder, matrix };
+        outline_glyph(glyf_data, loca_data, component, index_format, variations, &mut transformed)?;
+    }
+    
     Some(())
 }
+
+fn read_f2dot14(reader: &mut FontReader) -> Option<f32> {
+    Some(reader.read_i16().ok()? as f32 / 16384.0)
+}
+
+/// Applies a component's affine transform before passing points on
+struct Transformed<'b> {
+    inner: &'b mut dyn OutlineBuilder,
+    matrix: [f32; 6],
+}
+
+impl Transformed<'_> {
+    fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let [xx, yx, xy, yy, dx, dy] = self.matrix;
+        (x * xx + y * xy + dx, x * yx + y * yy + dy)
+    }
+}
+
+impl OutlineBuilder for Transformed<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.apply(x, y);
+        self.inner.move_to(x, y);
+    }
+    
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.apply(x, y);
+        self.inner.line_to(x, y);
+    }
+    
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x1, y1) = self.apply(x1, y1);
+        let (x, y) = self.apply(x, y);
+        self.inner.quad_to(x1, y1, x, y);
+    }
+    
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x1, y1) = self.apply(x1, y1);
+        let (x2, y2) = self.apply(x2, y2);
+        let (x, y) = self.apply(x, y);
+        self.inner.curve_to(x1, y1, x2, y2, x, y);
+    }
+    
+    fn close(&mut self) {
+        self.inner.close();
+    }
+}
@@ -8,9 +8,14 @@ mod tables;
 mod outline;
 mod cmap;
 mod glyf;
+mod variations;
 
 pub use reader::FontReader;
-pub use outline::{OutlineBuilder, GlyphOutline};
+pub use tables::{NameRecord, Os2Table, name_ids};
+pub use outline::{OutlineBuilder, GlyphOutline, OutlineCommand};
+use variations::GlyphVariations;
+
+use crate::font::variable::{VariableFont, VariableFontInstance};
 
 
 /// Glyph identifier
@@ -196,6 +201,18 @@ impl<'a> FontParser<'a> {
         self.line_gap
     }
     
+    /// Records of the name table
+    pub fn names(&self) -> Vec<NameRecord> {
+        self.table_data(b"name")
+            .and_then(|data| NameRecord::parse_table(data).ok())
+            .unwrap_or_default()
+    }
+    
+    /// OS/2 table
+    pub fn os2(&self) -> Option<Os2Table> {
+        Os2Table::parse(self.table_data(b"OS/2")?).ok()
+    }
+    
     /// Get glyph ID for character
     pub fn glyph_index(&self, c: char) -> Option<GlyphId> {
         let cmap_data = self.table_data(b"cmap")?;
@@ -249,7 +266,88 @@ impl<'a> FontParser<'a> {
         let glyf_data = self.table_data(b"glyf")?;
         let loca_data = self.table_data(b"loca")?;
         
-        glyf::outline_glyph(glyf_data, loca_data, glyph_id.0, self.index_format, builder)
+        glyf::outline_glyph(glyf_data, loca_data, glyph_id.0, self.index_format, None, builder)
+    }
+    
+    /// Axes and named instances of a variable font (`fvar`)
+    pub fn variable_font(&self) -> Option<VariableFont> {
+        variations::parse_fvar(self.table_data(b"fvar")?, &self.names())
+    }
+    
+    /// Normalized coordinates (-1..1 per `fvar` axis, remapped by `avar`)
+    /// of an instance; empty for static fonts
+    pub fn normalized_coords(&self, instance: &VariableFontInstance) -> Vec<f32> {
+        match self.variable_font() {
+            Some(font) => variations::normalize_coords(&font, instance, self.table_data(b"avar")),
+            None => Vec::new(),
+        }
+    }
+    
+    /// Outline a glyph at normalized variation coordinates
+    pub fn outline_glyph_with_coords<B: OutlineBuilder>(
+        &self,
+        glyph_id: GlyphId,
+        coords: &[f32],
+        builder: &mut B,
+    ) -> Option<()> {
+        let glyf_data = self.table_data(b"glyf")?;
+        let loca_data = self.table_data(b"loca")?;
+        let variations = self.table_data(b"gvar").and_then(|gvar| GlyphVariations::new(gvar, coords));
+        
+        glyf::outline_glyph(glyf_data, loca_data, glyph_id.0, self.index_format, variations.as_ref(), builder)
+    }
+    
+    /// Horizontal advance at normalized variation coordinates, from `HVAR`
+    /// or else the phantom points of `gvar`
+    pub fn glyph_hor_advance_with_coords(&self, glyph_id: GlyphId, coords: &[f32]) -> Option<f32> {
+        let advance = self.glyph_hor_advance(glyph_id)? as f32;
+        if coords.iter().all(|&c| c == 0.0) {
+            return Some(advance);
+        }
+        if let Some(hvar) = self.table_data(b"HVAR") {
+            return Some(advance + variations::hvar_advance_delta(hvar, glyph_id.0, coords).unwrap_or(0.0));
+        }
+        
+        let variations = self.table_data(b"gvar").and_then(|gvar| GlyphVariations::new(gvar, coords));
+        let point_count = self.glyph_point_count(glyph_id).unwrap_or(0);
+        let points = vec![(0.0, 0.0); point_count + 4];
+        let delta = variations
+            .and_then(|v| v.glyph_deltas(glyph_id.0, &points, &[]))
+            .map(|d| d[point_count + 1].0 - d[point_count].0)
+            .unwrap_or(0.0);
+        Some(advance + delta)
+    }
+    
+    /// Points of a simple glyph, or components of a composite one, as
+    /// `gvar` numbers them
+    fn glyph_point_count(&self, glyph_id: GlyphId) -> Option<usize> {
+        let glyf_data = self.table_data(b"glyf")?;
+        let loca_data = self.table_data(b"loca")?;
+        let offset = glyf::get_glyph_offset(loca_data, glyph_id.0, self.index_format)? as usize;
+        let next_offset = glyf::get_glyph_offset(loca_data, glyph_id.0 + 1, self.index_format)? as usize;
+        if offset == next_offset {
+            return Some(0);
+        }
+        
+        let mut reader = FontReader::new(glyf_data.get(offset..next_offset)?);
+        let num_contours = reader.read_i16().ok()?;
+        reader.skip(8).ok()?;
+        if num_contours >= 0 {
+            reader.skip((num_contours as usize).checked_sub(1)? * 2).ok()?;
+            return Some(reader.read_u16().ok()? as usize + 1);
+        }
+        let mut count = 0;
+        loop {
+            let flags = reader.read_u16().ok()?;
+            reader.skip(2).ok()?;
+            let args = if flags & 0x0001 != 0 { 4 } else { 2 };
+            let transform = if flags & 0x0008 != 0 { 2 } else if flags & 0x0040 != 0 { 4 } else if flags & 0x0080 != 0 { 8 } else { 0 };
+            reader.skip(args + transform).ok()?;
+            count += 1;
+            if flags & 0x0020 == 0 {
+                return Some(count);
+            }
+        }
     }
 }
 
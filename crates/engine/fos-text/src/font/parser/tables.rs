@@ -153,3 +153,51 @@ pub mod name_ids {
     pub const TYPOGRAPHIC_FAMILY: u16 = 16;
     pub const TYPOGRAPHIC_SUBFAMILY: u16 = 17;
 }
+
+impl NameRecord {
+    /// Parse the Unicode and Macintosh records of a name table
+    pub fn parse_table(data: &[u8]) -> Result<Vec<Self>, ParseError> {
+        let mut r = FontReader::new(data);
+        let _format = r.read_u16()?;
+        let count = r.read_u16()?;
+        let storage_offset = r.read_u16()? as usize;
+
+        let mut records = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let platform_id = r.read_u16()?;
+            let encoding_id = r.read_u16()?;
+            let language_id = r.read_u16()?;
+            let name_id = r.read_u16()?;
+            let length = r.read_u16()? as usize;
+            let offset = r.read_u16()? as usize;
+
+            let start = storage_offset + offset;
+            let Some(bytes) = data.get(start..start + length) else { continue };
+            let name = match platform_id {
+                // Unicode and Windows: UTF-16BE
+                0 | 3 => {
+                    let units: Vec<u16> = bytes.chunks_exact(2).map(|b| u16::from_be_bytes([b[0], b[1]])).collect();
+                    String::from_utf16_lossy(&units)
+                }
+                // Macintosh Roman; names are ASCII in practice
+                1 => bytes.iter().map(|&b| if b.is_ascii() { b as char } else { '\u{FFFD}' }).collect(),
+                _ => continue,
+            };
+            records.push(Self { platform_id, encoding_id, language_id, name_id, name });
+        }
+        Ok(records)
+    }
+
+    /// Best name with the given ID: English Windows names first
+    pub fn find(records: &[Self], name_id: u16) -> Option<&str> {
+        let candidates = records.iter().filter(|r| r.name_id == name_id);
+        candidates
+            .min_by_key(|r| match (r.platform_id, r.language_id) {
+                (3, 0x0409) => 0,
+                (3, _) => 1,
+                (0, _) => 2,
+                _ => 3,
+            })
+            .map(|r| r.name.as_str())
+    }
+}
@@ -0,0 +1,529 @@
+//! Font variation tables (fvar, avar, gvar, HVAR)
+//!
+//! `fvar` defines the design axes and named instances. Coordinates are
+//! normalized to -1..1 around the axis default and remapped through `avar`.
+//! `gvar` stores point deltas per glyph and `HVAR` advance deltas; each
+//! delta set applies to a region of the design space and is scaled by how
+//! close the coordinates are to the region's peak.
+
+use super::reader::FontReader;
+use super::tables::{NameRecord, name_ids};
+use crate::font::fixed_point::Fixed16;
+use crate::font::variable::{FontAxis, NamedInstance, VariableFont, VariableFontInstance};
+
+/// Parse `fvar` into axes and named instances
+pub fn parse_fvar(data: &[u8], names: &[NameRecord]) -> Option<VariableFont> {
+    let mut r = FontReader::new(data);
+    r.skip(4).ok()?; // version
+    let axes_offset = r.read_u16().ok()? as usize;
+    r.skip(2).ok()?; // reserved
+    let axis_count = r.read_u16().ok()? as usize;
+    let axis_size = r.read_u16().ok()? as usize;
+    let instance_count = r.read_u16().ok()? as usize;
+    let instance_size = r.read_u16().ok()? as usize;
+
+    let family = NameRecord::find(names, name_ids::TYPOGRAPHIC_FAMILY)
+        .or_else(|| NameRecord::find(names, name_ids::FAMILY))
+        .unwrap_or("");
+    let mut font = VariableFont::new(family);
+    for i in 0..axis_count {
+        let mut r = FontReader::new(data.get(axes_offset + i * axis_size..)?);
+        let tag = r.read_tag().ok()?;
+        let min = read_fixed16(&mut r)?;
+        let default = read_fixed16(&mut r)?;
+        let max = read_fixed16(&mut r)?;
+        let _flags = r.read_u16().ok()?;
+        let name_id = r.read_u16().ok()?;
+        let name = NameRecord::find(names, name_id).map(str::to_string).unwrap_or_else(|| String::from_utf8_lossy(&tag).to_string());
+        font.add_axis(FontAxis::new_fixed(tag, &name, min, default, max));
+    }
+
+    let instances_offset = axes_offset + axis_count * axis_size;
+    for i in 0..instance_count {
+        let mut r = FontReader::new(data.get(instances_offset + i * instance_size..)?);
+        let name_id = r.read_u16().ok()?;
+        r.skip(2).ok()?; // flags
+        let mut coordinates = VariableFontInstance::new();
+        for axis in &font.axes {
+            coordinates.set_axis(axis.tag, read_fixed16(&mut r)?);
+        }
+        let name = NameRecord::find(names, name_id).unwrap_or("").to_string();
+        font.add_named_instance(NamedInstance::new(&name, coordinates));
+    }
+    Some(font)
+}
+
+/// Normalized coordinates of an instance, one per axis in `fvar` order,
+/// quantized to F2Dot14 like the coordinates stored in the tables
+pub fn normalize_coords(font: &VariableFont, instance: &VariableFontInstance, avar: Option<&[u8]>) -> Vec<f32> {
+    let mut coords: Vec<f32> = font
+        .axes
+        .iter()
+        .map(|axis| {
+            let value = axis.clamp(instance.get_axis(&axis.tag).unwrap_or(axis.default_value)).to_f32();
+            let (min, default, max) = (axis.min_f32(), axis.default_f32(), axis.max_f32());
+            if value < default {
+                (value - default) / (default - min)
+            } else if value > default {
+                (value - default) / (max - default)
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    if let Some(avar) = avar {
+        apply_avar(avar, &mut coords);
+    }
+    coords.iter().map(|&c| f2dot14(c)).collect()
+}
+
+/// Remap normalized coordinates through the `avar` segment maps
+fn apply_avar(data: &[u8], coords: &mut [f32]) -> Option<()> {
+    let mut r = FontReader::new(data);
+    r.skip(6).ok()?; // version, reserved
+    let axis_count = r.read_u16().ok()? as usize;
+    for coord in coords.iter_mut().take(axis_count) {
+        let pair_count = r.read_u16().ok()? as usize;
+        let mut pairs = Vec::with_capacity(pair_count);
+        for _ in 0..pair_count {
+            pairs.push((read_f2dot14(&mut r)?, read_f2dot14(&mut r)?));
+        }
+        *coord = map_segments(*coord, &pairs);
+    }
+    Some(())
+}
+
+/// Piecewise-linear `avar` mapping
+fn map_segments(coord: f32, pairs: &[(f32, f32)]) -> f32 {
+    let (Some(first), Some(last)) = (pairs.first(), pairs.last()) else {
+        return coord;
+    };
+    if coord <= first.0 {
+        return first.1;
+    }
+    if coord >= last.0 {
+        return last.1;
+    }
+    for window in pairs.windows(2) {
+        let ((from0, to0), (from1, to1)) = (window[0], window[1]);
+        if coord <= from1 {
+            if from1 == from0 {
+                return to1;
+            }
+            return to0 + (coord - from0) * (to1 - to0) / (from1 - from0);
+        }
+    }
+    coord
+}
+
+/// Scalar of one axis of a variation region
+fn axis_scalar(coord: f32, start: f32, peak: f32, end: f32) -> f32 {
+    if peak == 0.0 || coord == peak {
+        return 1.0;
+    }
+    // Invalid regions do not constrain the axis
+    if start > peak || peak > end || (start < 0.0 && end > 0.0) {
+        return 1.0;
+    }
+    if coord <= start || coord >= end {
+        return 0.0;
+    }
+    if coord < peak {
+        (coord - start) / (peak - start)
+    } else {
+        (end - coord) / (end - peak)
+    }
+}
+
+/// Glyph variations (`gvar`) at a set of normalized coordinates
+#[derive(Debug, Clone)]
+pub struct GlyphVariations<'a> {
+    data: &'a [u8],
+    axis_count: usize,
+    shared_tuples: Vec<Vec<f32>>,
+    glyph_count: u16,
+    long_offsets: bool,
+    data_offset: usize,
+    coords: Vec<f32>,
+}
+
+impl<'a> GlyphVariations<'a> {
+    pub fn new(data: &'a [u8], coords: &[f32]) -> Option<Self> {
+        let mut r = FontReader::new(data);
+        r.skip(4).ok()?; // version
+        let axis_count = r.read_u16().ok()? as usize;
+        let shared_tuple_count = r.read_u16().ok()? as usize;
+        let shared_tuples_offset = r.read_u32().ok()? as usize;
+        let glyph_count = r.read_u16().ok()?;
+        let flags = r.read_u16().ok()?;
+        let data_offset = r.read_u32().ok()? as usize;
+
+        let mut tuples = FontReader::new(data.get(shared_tuples_offset..)?);
+        let mut shared_tuples = Vec::with_capacity(shared_tuple_count);
+        for _ in 0..shared_tuple_count {
+            shared_tuples.push(read_tuple(&mut tuples, axis_count)?);
+        }
+        Some(Self {
+            data,
+            axis_count,
+            shared_tuples,
+            glyph_count,
+            long_offsets: flags & 1 != 0,
+            data_offset,
+            coords: coords.to_vec(),
+        })
+    }
+
+    /// Whether any coordinate differs from the default
+    pub fn is_default(&self) -> bool {
+        self.coords.iter().all(|&c| c == 0.0)
+    }
+
+    fn glyph_data(&self, glyph: u16) -> Option<&'a [u8]> {
+        if glyph >= self.glyph_count {
+            return None;
+        }
+        let mut r = FontReader::new(self.data.get(20..)?);
+        let (start, end) = if self.long_offsets {
+            r.skip(glyph as usize * 4).ok()?;
+            (r.read_u32().ok()? as usize, r.read_u32().ok()? as usize)
+        } else {
+            r.skip(glyph as usize * 2).ok()?;
+            (r.read_u16().ok()? as usize * 2, r.read_u16().ok()? as usize * 2)
+        };
+        if start >= end {
+            return None;
+        }
+        self.data.get(self.data_offset + start..self.data_offset + end)
+    }
+
+    /// Deltas for the points of a glyph. `points` are the original points
+    /// followed by the four phantom points; `end_points` are the contour
+    /// ends used to infer deltas of untouched points, empty for composite
+    /// glyphs whose points are component offsets.
+    pub fn glyph_deltas(&self, glyph: u16, points: &[(f32, f32)], end_points: &[usize]) -> Option<Vec<(f32, f32)>> {
+        if self.is_default() {
+            return None;
+        }
+        let data = self.glyph_data(glyph)?;
+        let mut r = FontReader::new(data);
+        let count = r.read_u16().ok()?;
+        let serialized_offset = r.read_u16().ok()? as usize;
+        let mut serialized = FontReader::new(data.get(serialized_offset..)?);
+        let shared_points = if count & 0x8000 != 0 { read_packed_points(&mut serialized)? } else { None };
+
+        let n = points.len();
+        let mut deltas = vec![(0.0, 0.0); n];
+        for _ in 0..(count & 0x0FFF) {
+            let size = r.read_u16().ok()? as usize;
+            let tuple_index = r.read_u16().ok()?;
+            let peak = if tuple_index & 0x8000 != 0 {
+                read_tuple(&mut r, self.axis_count)?
+            } else {
+                self.shared_tuples.get((tuple_index & 0x0FFF) as usize)?.clone()
+            };
+            let (start, end) = if tuple_index & 0x4000 != 0 {
+                (read_tuple(&mut r, self.axis_count)?, read_tuple(&mut r, self.axis_count)?)
+            } else {
+                (peak.iter().map(|&p| p.min(0.0)).collect(), peak.iter().map(|&p| p.max(0.0)).collect())
+            };
+            let tuple_data = serialized.read_bytes(size).ok()?;
+
+            let scalar: f32 = (0..self.axis_count)
+                .map(|i| axis_scalar(self.coords.get(i).copied().unwrap_or(0.0), start[i], peak[i], end[i]))
+                .product();
+            if scalar == 0.0 {
+                continue;
+            }
+
+            let mut t = FontReader::new(tuple_data);
+            let tuple_points = if tuple_index & 0x2000 != 0 { read_packed_points(&mut t)? } else { shared_points.clone() };
+            match tuple_points {
+                None => {
+                    let xs = read_packed_deltas(&mut t, n)?;
+                    let ys = read_packed_deltas(&mut t, n)?;
+                    for (i, delta) in deltas.iter_mut().enumerate() {
+                        delta.0 += xs[i] * scalar;
+                        delta.1 += ys[i] * scalar;
+                    }
+                }
+                Some(indices) => {
+                    let xs = read_packed_deltas(&mut t, indices.len())?;
+                    let ys = read_packed_deltas(&mut t, indices.len())?;
+                    let mut touched = vec![None; n];
+                    for (k, &index) in indices.iter().enumerate() {
+                        if let Some(slot) = touched.get_mut(index as usize) {
+                            *slot = Some((xs[k], ys[k]));
+                        }
+                    }
+                    interpolate_untouched(&mut touched, points, end_points);
+                    for (delta, d) in deltas.iter_mut().zip(touched) {
+                        if let Some((dx, dy)) = d {
+                            delta.0 += dx * scalar;
+                            delta.1 += dy * scalar;
+                        }
+                    }
+                }
+            }
+        }
+        Some(deltas)
+    }
+}
+
+/// Infer deltas of points a tuple leaves out from the nearest touched
+/// points on the same contour (IUP)
+fn interpolate_untouched(deltas: &mut [Option<(f32, f32)>], points: &[(f32, f32)], end_points: &[usize]) {
+    let mut start = 0;
+    for &end in end_points {
+        if end >= deltas.len() || end < start {
+            break;
+        }
+        let touched: Vec<usize> = (start..=end).filter(|&i| deltas[i].is_some()).collect();
+        for (k, &from) in touched.iter().enumerate() {
+            let to = touched[(k + 1) % touched.len()];
+            let (d1, d2) = (deltas[from].unwrap_or_default(), deltas[to].unwrap_or_default());
+            let (p1, p2) = (points[from], points[to]);
+            let mut i = from;
+            loop {
+                i = if i == end { start } else { i + 1 };
+                if i == to || deltas[i].is_some() {
+                    break;
+                }
+                deltas[i] = Some((
+                    interpolate(points[i].0, p1.0, p2.0, d1.0, d2.0),
+                    interpolate(points[i].1, p1.1, p2.1, d1.1, d2.1),
+                ));
+            }
+        }
+        start = end + 1;
+    }
+}
+
+fn interpolate(coord: f32, c1: f32, c2: f32, d1: f32, d2: f32) -> f32 {
+    if c1 == c2 {
+        return if d1 == d2 { d1 } else { 0.0 };
+    }
+    let (lo, hi, lo_delta, hi_delta) = if c1 < c2 { (c1, c2, d1, d2) } else { (c2, c1, d2, d1) };
+    if coord <= lo {
+        lo_delta
+    } else if coord >= hi {
+        hi_delta
+    } else {
+        lo_delta + (coord - lo) * (hi_delta - lo_delta) / (hi - lo)
+    }
+}
+
+/// Advance width delta from `HVAR`
+pub fn hvar_advance_delta(data: &[u8], glyph: u16, coords: &[f32]) -> Option<f32> {
+    let mut r = FontReader::new(data);
+    r.skip(4).ok()?; // version
+    let store_offset = r.read_u32().ok()? as usize;
+    let mapping_offset = r.read_u32().ok()? as usize;
+    let (outer, inner) = if mapping_offset == 0 {
+        (0, glyph)
+    } else {
+        delta_set_index(data.get(mapping_offset..)?, glyph)?
+    };
+    item_variation_delta(data.get(store_offset..)?, outer, inner, coords)
+}
+
+/// Look up a glyph in a DeltaSetIndexMap
+fn delta_set_index(data: &[u8], glyph: u16) -> Option<(u16, u16)> {
+    let mut r = FontReader::new(data);
+    let format = r.read_u8().ok()?;
+    let entry_format = r.read_u8().ok()?;
+    let map_count = if format == 0 { r.read_u16().ok()? as usize } else { r.read_u32().ok()? as usize };
+    if map_count == 0 {
+        return None;
+    }
+    let entry_size = ((entry_format >> 4) & 0x3) as usize + 1;
+    let inner_bits = (entry_format & 0x0F) as u32 + 1;
+    r.skip((glyph as usize).min(map_count - 1) * entry_size).ok()?;
+    let mut entry = 0u32;
+    for _ in 0..entry_size {
+        entry = (entry << 8) | r.read_u8().ok()? as u32;
+    }
+    Some(((entry >> inner_bits) as u16, (entry & ((1 << inner_bits) - 1)) as u16))
+}
+
+/// Delta of one item of an ItemVariationStore
+fn item_variation_delta(data: &[u8], outer: u16, inner: u16, coords: &[f32]) -> Option<f32> {
+    let mut r = FontReader::new(data);
+    r.skip(2).ok()?; // format
+    let region_list_offset = r.read_u32().ok()? as usize;
+    let data_count = r.read_u16().ok()?;
+    if outer >= data_count {
+        return None;
+    }
+    r.skip(outer as usize * 4).ok()?;
+    let item_data_offset = r.read_u32().ok()? as usize;
+
+    let mut regions = FontReader::new(data.get(region_list_offset..)?);
+    let axis_count = regions.read_u16().ok()? as usize;
+    let region_count = regions.read_u16().ok()? as usize;
+    let regions_data = regions.slice_from_here();
+
+    let mut item = FontReader::new(data.get(item_data_offset..)?);
+    let item_count = item.read_u16().ok()?;
+    let word_delta_count = item.read_u16().ok()?;
+    let region_index_count = item.read_u16().ok()? as usize;
+    if inner >= item_count {
+        return None;
+    }
+    let long_words = word_delta_count & 0x8000 != 0;
+    let word_count = (word_delta_count & 0x7FFF) as usize;
+    let mut region_indices = Vec::with_capacity(region_index_count);
+    for _ in 0..region_index_count {
+        region_indices.push(item.read_u16().ok()? as usize);
+    }
+    let row_size = if long_words {
+        word_count * 4 + (region_index_count - word_count) * 2
+    } else {
+        word_count * 2 + region_index_count.saturating_sub(word_count)
+    };
+    item.skip(inner as usize * row_size).ok()?;
+
+    let mut delta = 0.0;
+    for (j, &region) in region_indices.iter().enumerate() {
+        let value = match (j < word_count, long_words) {
+            (true, true) => item.read_u32().ok()? as i32 as f32,
+            (true, false) | (false, true) => item.read_i16().ok()? as f32,
+            (false, false) => item.read_u8().ok()? as i8 as f32,
+        };
+        if region >= region_count {
+            continue;
+        }
+        let mut axes = FontReader::new(regions_data.get(region * axis_count * 6..)?);
+        let mut scalar = 1.0;
+        for axis in 0..axis_count {
+            let start = read_f2dot14(&mut axes)?;
+            let peak = read_f2dot14(&mut axes)?;
+            let end = read_f2dot14(&mut axes)?;
+            scalar *= axis_scalar(coords.get(axis).copied().unwrap_or(0.0), start, peak, end);
+        }
+        delta += value * scalar;
+    }
+    Some(delta)
+}
+
+/// Packed point numbers; `None` means every point of the glyph
+fn read_packed_points(r: &mut FontReader) -> Option<Option<Vec<u16>>> {
+    let first = r.read_u8().ok()? as usize;
+    if first == 0 {
+        return Some(None);
+    }
+    let count = if first & 0x80 != 0 { ((first & 0x7F) << 8) | r.read_u8().ok()? as usize } else { first };
+    let mut points = Vec::with_capacity(count);
+    let mut last = 0u16;
+    while points.len() < count {
+        let control = r.read_u8().ok()?;
+        for _ in 0..(control & 0x7F) as usize + 1 {
+            let delta = if control & 0x80 != 0 { r.read_u16().ok()? } else { r.read_u8().ok()? as u16 };
+            last = last.wrapping_add(delta);
+            points.push(last);
+        }
+    }
+    points.truncate(count);
+    Some(Some(points))
+}
+
+/// Packed deltas: runs of zero, byte, word or long values
+fn read_packed_deltas(r: &mut FontReader, count: usize) -> Option<Vec<f32>> {
+    let mut deltas = Vec::with_capacity(count);
+    while deltas.len() < count {
+        let control = r.read_u8().ok()?;
+        for _ in 0..(control & 0x3F) as usize + 1 {
+            let delta = match control & 0xC0 {
+                0x80 => 0,
+                0x40 => r.read_i16().ok()? as i32,
+                0xC0 => r.read_u32().ok()? as i32,
+                _ => r.read_u8().ok()? as i8 as i32,
+            };
+            deltas.push(delta as f32);
+        }
+    }
+    deltas.truncate(count);
+    Some(deltas)
+}
+
+fn read_tuple(r: &mut FontReader, axis_count: usize) -> Option<Vec<f32>> {
+    (0..axis_count).map(|_| read_f2dot14(r)).collect()
+}
+
+fn read_f2dot14(r: &mut FontReader) -> Option<f32> {
+    Some(r.read_i16().ok()? as f32 / 16384.0)
+}
+
+fn read_fixed16(r: &mut FontReader) -> Option<Fixed16> {
+    Some(Fixed16::from_bits(r.read_u32().ok()? as i32))
+}
+
+fn f2dot14(value: f32) -> f32 {
+    (value.clamp(-2.0, 2.0) * 16384.0).round() / 16384.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push16(out: &mut Vec<u8>, v: u16) {
+        out.extend_from_slice(&v.to_be_bytes());
+    }
+
+    #[test]
+    fn test_gvar_deltas() {
+        // One axis, one glyph whose only tuple moves points 0 and 2 at wght=max
+        let mut gvar = Vec::new();
+        for v in [1, 0, 1, 0] {
+            push16(&mut gvar, v);
+        }
+        gvar.extend_from_slice(&20u32.to_be_bytes()); // shared tuples (none)
+        push16(&mut gvar, 1); // glyph count
+        push16(&mut gvar, 0); // short offsets
+        gvar.extend_from_slice(&24u32.to_be_bytes());
+        push16(&mut gvar, 0);
+        push16(&mut gvar, 9); // 18 bytes
+        push16(&mut gvar, 1); // one tuple
+        push16(&mut gvar, 10); // serialized data offset
+        push16(&mut gvar, 8); // tuple data size
+        push16(&mut gvar, 0x8000 | 0x2000); // embedded peak, private points
+        push16(&mut gvar, 0x4000); // peak 1.0
+        gvar.extend_from_slice(&[2, 0x01, 0, 2]); // points 0 and 2
+        gvar.extend_from_slice(&[0x01, 10, 20]); // x deltas
+        gvar.extend_from_slice(&[0x81]); // y deltas: two zeros
+
+        let square = [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0), (0.0, 0.0), (100.0, 0.0), (0.0, 0.0), (0.0, 0.0)];
+        let variations = GlyphVariations::new(&gvar, &[0.5]).unwrap();
+        let deltas = variations.glyph_deltas(0, &square, &[3]).unwrap();
+        // Points 1 and 3 are inferred from their touched neighbours
+        let xs: Vec<f32> = deltas.iter().map(|d| d.0).collect();
+        assert_eq!(xs, [5.0, 10.0, 10.0, 5.0, 0.0, 0.0, 0.0, 0.0]);
+        assert!(deltas.iter().all(|d| d.1 == 0.0));
+
+        assert!(GlyphVariations::new(&gvar, &[0.0]).unwrap().glyph_deltas(0, &square, &[3]).is_none());
+        assert_eq!(GlyphVariations::new(&gvar, &[-0.5]).unwrap().glyph_deltas(0, &square, &[3]).unwrap()[0], (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut font = VariableFont::new("Test");
+        font.add_axis(FontAxis::new(*b"wght", "Weight", 100.0, 400.0, 900.0));
+        let mut instance = VariableFontInstance::new();
+        instance.set_weight(650.0);
+        assert_eq!(normalize_coords(&font, &instance, None), [0.5]);
+        instance.set_weight(50.0);
+        assert_eq!(normalize_coords(&font, &instance, None), [-1.0]);
+
+        // avar pulling 0.5 down to 0.25
+        let mut avar = Vec::new();
+        for v in [1, 0, 0, 1, 4] {
+            push16(&mut avar, v);
+        }
+        for (from, to) in [(-1.0f32, -1.0f32), (0.0, 0.0), (0.5, 0.25), (1.0, 1.0)] {
+            push16(&mut avar, (from * 16384.0) as i16 as u16);
+            push16(&mut avar, (to * 16384.0) as i16 as u16);
+        }
+        instance.set_weight(775.0);
+        assert_eq!(normalize_coords(&font, &instance, Some(&avar)), [0.625]);
+    }
+}
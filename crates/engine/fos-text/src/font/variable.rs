@@ -3,9 +3,17 @@
 //! Implementation of OpenType variable fonts (OpenType 1.8+).
 //! Variable fonts allow continuous variation along design axes.
 //! Uses Fixed16 for deterministic, cross-platform axis calculations.
+//!
+//! CSS selects an instance from `font-named-instance`, `font-weight`,
+//! `font-stretch`, `font-style`, `font-optical-sizing` and
+//! `font-variation-settings`; see [`VariableFont::instance_for_style`].
 
 use std::collections::HashMap;
 use super::fixed_point::Fixed16;
+use super::FontStyle;
+
+/// Slant of `font-style: oblique` without an angle, in degrees
+pub const DEFAULT_OBLIQUE_ANGLE: f32 = 14.0;
 
 /// Variable font axis definition
 #[derive(Debug, Clone)]
@@ -258,12 +266,118 @@ impl VariableFont {
         result
     }
     
+    /// Named instance by name (`font-named-instance`), ASCII
+    /// case-insensitive
+    pub fn named_instance(&self, name: &str) -> Option<&NamedInstance> {
+        self.named_instances.iter().find(|i| i.name.eq_ignore_ascii_case(name.trim()))
+    }
+    
+    /// Axis coordinates for computed CSS font properties, applied in the
+    /// order of CSS Fonts 4: axis defaults, the named instance, weight,
+    /// stretch and style, optical sizing, then `font-variation-settings`.
+    /// Every value is clamped to its axis.
+    pub fn instance_for_style(&self, style: &FontVariationStyle) -> VariableFontInstance {
+        let mut instance = self.default_instance();
+        let named = style.named_instance.as_deref().and_then(|name| self.named_instance(name));
+        if let Some(named) = named {
+            for (&tag, &value) in &named.coordinates.coordinates {
+                instance.set_axis(tag, value);
+            }
+        }
+        
+        if let Some(weight) = style.weight {
+            instance.set_weight(weight);
+        }
+        if let Some(stretch) = style.stretch {
+            instance.set_width(stretch);
+        }
+        let has_italic = self.has_axis(&axis_tags::ITALIC);
+        let has_slant = self.has_axis(&axis_tags::SLANT);
+        match style.style {
+            FontStyle::Normal => {
+                if has_italic {
+                    instance.set_italic(0.0);
+                }
+                if has_slant {
+                    instance.set_slant(0.0);
+                }
+            }
+            FontStyle::Italic if has_italic => instance.set_italic(1.0),
+            FontStyle::Italic | FontStyle::Oblique => {
+                // slnt counts counter-clockwise, CSS oblique angles clockwise
+                let angle = style.oblique_angle.unwrap_or(DEFAULT_OBLIQUE_ANGLE);
+                if has_slant {
+                    instance.set_slant(-angle);
+                } else if has_italic && angle > 0.0 {
+                    instance.set_italic(1.0);
+                }
+            }
+        }
+        if let Some(size) = style.optical_size {
+            instance.set_axis_f32(axis_tags::OPTICAL_SIZE, size);
+        }
+        for &(tag, value) in &style.settings {
+            instance.set_axis_f32(tag, value);
+        }
+        
+        let mut clamped = VariableFontInstance::new();
+        for axis in &self.axes {
+            if let Some(value) = instance.get_axis(&axis.tag) {
+                clamped.set_axis(axis.tag, axis.clamp(value));
+            }
+        }
+        clamped
+    }
+    
     /// Interpolate with f32 t value (convenience)
     pub fn interpolate_f32(&self, a: &VariableFontInstance, b: &VariableFontInstance, t: f32) -> VariableFontInstance {
         self.interpolate(a, b, Fixed16::from_f32(t))
     }
 }
 
+/// Computed CSS font properties that select a variable font instance
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FontVariationStyle {
+    /// `font-named-instance` of the `@font-face` rule
+    pub named_instance: Option<String>,
+    /// `font-weight`
+    pub weight: Option<f32>,
+    /// `font-stretch` as a percentage
+    pub stretch: Option<f32>,
+    /// `font-style`
+    pub style: FontStyle,
+    /// Angle of `font-style: oblique <angle>` in degrees
+    pub oblique_angle: Option<f32>,
+    /// Font size in px when `font-optical-sizing: auto`
+    pub optical_size: Option<f32>,
+    /// `font-variation-settings`, applied last
+    pub settings: Vec<([u8; 4], f32)>,
+}
+
+/// Parse `font-variation-settings`: `normal` or a list like
+/// `"wght" 650, "GRAD" 50`
+pub fn parse_font_variation_settings(value: &str) -> Option<Vec<([u8; 4], f32)>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("normal") {
+        return Some(Vec::new());
+    }
+    value
+        .split(',')
+        .map(|setting| {
+            let setting = setting.trim();
+            let quote = setting.chars().next().filter(|&c| c == '"' || c == '\'')?;
+            let rest = &setting[1..];
+            let end = rest.find(quote)?;
+            let tag: [u8; 4] = rest.as_bytes()[..end].try_into().ok()?;
+            if !tag.iter().all(|b| (0x20..=0x7E).contains(b)) {
+                return None;
+            }
+            let number: f32 = rest[end + 1..].trim().parse().ok()?;
+            number.is_finite().then_some((tag, number))
+        })
+        .collect()
+}
+
 /// Named instance (preset) for variable fonts
 #[derive(Debug, Clone)]
 pub struct NamedInstance {
@@ -326,6 +440,36 @@ mod tests {
         assert!((weight - 500.0).abs() < 1.0);
     }
     
+    #[test]
+    fn test_instance_for_style() {
+        let mut font = VariableFont::new("Test Font");
+        font.add_axis(FontAxis::new(*b"wght", "Weight", 100.0, 400.0, 900.0));
+        font.add_axis(FontAxis::new(*b"slnt", "Slant", -10.0, 0.0, 0.0));
+        font.add_axis(FontAxis::new(*b"opsz", "Optical size", 8.0, 14.0, 144.0));
+        let mut light_coords = VariableFontInstance::new();
+        light_coords.set_weight(300.0);
+        font.add_named_instance(NamedInstance::new("Light", light_coords));
+        
+        let style = FontVariationStyle {
+            named_instance: Some("light".into()),
+            style: FontStyle::Oblique,
+            optical_size: Some(48.0),
+            settings: parse_font_variation_settings("\"wght\" 650, 'XTRA' 2").unwrap(),
+            ..Default::default()
+        };
+        let instance = font.instance_for_style(&style);
+        // Settings win over the named instance; slant is clamped; unknown axes dropped
+        assert_eq!(instance.get_axis_f32(&axis_tags::WEIGHT), Some(650.0));
+        assert_eq!(instance.get_axis_f32(&axis_tags::SLANT), Some(-10.0));
+        assert_eq!(instance.get_axis_f32(&axis_tags::OPTICAL_SIZE), Some(48.0));
+        assert_eq!(instance.get_axis_f32(b"XTRA"), None);
+        
+        let light = font.instance_for_style(&FontVariationStyle { named_instance: Some("Light".into()), ..Default::default() });
+        assert_eq!(light.get_axis_f32(&axis_tags::WEIGHT), Some(300.0));
+        assert_eq!(parse_font_variation_settings("normal"), Some(Vec::new()));
+        assert_eq!(parse_font_variation_settings("\"wg\" 1"), None);
+    }
+    
     #[test]
     fn test_variable_font_default() {
         let mut font = VariableFont::new("Test Font");
@@ -4,13 +4,12 @@
 //! - WOFF2 container parsing
 //! - Brotli decompression with WOFF2 dictionary
 //! - Table transformation (glyf/loca/hmtx reconstruction)
-//! - OpenType container reconstruction
+//! - OpenType container reconstruction (including collections)
 
-use super::brotli::{decompress_with_dict, BrotliError, WOFF2_DICTIONARY};
+use super::brotli::{BrotliDecoder, BrotliError};
 use super::parser::reader::FontReader;
 use super::woff2_transforms::{
-    reconstruct_glyf, reconstruct_hmtx, generate_loca, can_use_short_loca,
-    requires_transform, TransformError,
+    reconstruct_glyf, reconstruct_hmtx, requires_transform, TransformError,
 };
 
 /// WOFF2 signature 'wOF2'
@@ -127,154 +126,244 @@ struct Woff2TableEntry {
     orig_length: u32,
     /// Transform length (only if transformed)
     transform_length: Option<u32>,
-    /// Flags (bit 0-5: known tag index, bit 6: transform applied, bit 7: arbitrary tag)
+    /// Flags (bit 0-5: known tag index, 63 = arbitrary tag; bit 6-7: transform version)
     flags: u8,
 }
 
 impl Woff2TableEntry {
     /// Check if table has transformation applied
     fn has_transform(&self) -> bool {
-        // For glyf and loca, transform is applied by default unless flag says otherwise
-        let is_glyf_or_loca = self.tag == *b"glyf" || self.tag == *b"loca";
-        
-        if is_glyf_or_loca {
-            // Bit 6 set means transform is nullified (not applied)
-            (self.flags & 0x40) == 0
+        let version = self.flags >> 6;
+        if self.tag == *b"glyf" || self.tag == *b"loca" {
+            // Version 0 is the glyf/loca transform, version 3 the null transform
+            version == 0
         } else {
-            // For other tables, bit 6 set means transform is applied
-            (self.flags & 0x40) != 0
+            version != 0
         }
     }
+
+    /// Number of bytes the table occupies in the decompressed stream
+    fn stored_length(&self) -> u32 {
+        self.transform_length.unwrap_or(self.orig_length)
+    }
 }
 
 /// Read UIntBase128 variable-length encoding
 fn read_uint_base128(reader: &mut FontReader) -> Woff2Result<u32> {
     let mut result = 0u32;
-    
+
     for i in 0..5 {
         let byte = reader.read_u8().map_err(|_| Woff2Error::InvalidTableDirectory)?;
-        
+
         // Check for overflow
         if i == 0 && byte == 0x80 {
             return Err(Woff2Error::InvalidTableDirectory);
         }
-        if result > 0x0FFFFFFF {
+        if result > 0x01FFFFFF {
             return Err(Woff2Error::InvalidTableDirectory);
         }
-        
+
         result = (result << 7) | (byte & 0x7F) as u32;
-        
+
         if byte & 0x80 == 0 {
             return Ok(result);
         }
     }
-    
+
     Err(Woff2Error::InvalidTableDirectory)
 }
 
 /// Read 255UInt16 variable-length encoding
 fn read_255_uint16(reader: &mut FontReader) -> Woff2Result<u16> {
+    const WORD_CODE: u8 = 253;
+    const ONE_MORE_BYTE_CODE2: u8 = 254;
+    const ONE_MORE_BYTE_CODE1: u8 = 255;
+    const LOWEST_U_CODE: u16 = 253;
+
     let first = reader.read_u8().map_err(|_| Woff2Error::InvalidTableDirectory)?;
-    
-    if first < 253 {
-        Ok(first as u16)
-    } else if first == 253 {
-        let second = reader.read_u8().map_err(|_| Woff2Error::InvalidTableDirectory)?;
-        Ok(253 + second as u16)
-    } else if first == 254 {
-        let hi = reader.read_u8().map_err(|_| Woff2Error::InvalidTableDirectory)? as u16;
-        let lo = reader.read_u8().map_err(|_| Woff2Error::InvalidTableDirectory)? as u16;
-        Ok(253 + 256 + (hi << 8) + lo)
-    } else {
-        // first == 255
-        let hi = reader.read_u8().map_err(|_| Woff2Error::InvalidTableDirectory)? as u16;
-        let lo = reader.read_u8().map_err(|_| Woff2Error::InvalidTableDirectory)? as u16;
-        Ok((hi << 8) + lo)
+    match first {
+        WORD_CODE => reader.read_u16().map_err(|_| Woff2Error::InvalidTableDirectory),
+        ONE_MORE_BYTE_CODE1 => {
+            let next = reader.read_u8().map_err(|_| Woff2Error::InvalidTableDirectory)?;
+            Ok(next as u16 + LOWEST_U_CODE)
+        }
+        ONE_MORE_BYTE_CODE2 => {
+            let next = reader.read_u8().map_err(|_| Woff2Error::InvalidTableDirectory)?;
+            Ok(next as u16 + LOWEST_U_CODE * 2)
+        }
+        _ => Ok(first as u16),
     }
 }
 
 /// Parse table directory
 fn parse_table_directory(reader: &mut FontReader, num_tables: u16) -> Woff2Result<Vec<Woff2TableEntry>> {
     let mut entries = Vec::with_capacity(num_tables as usize);
-    
+
     for _ in 0..num_tables {
         let flags = reader.read_u8().map_err(|_| Woff2Error::InvalidTableDirectory)?;
-        
+
         // Get tag
         let tag = if (flags & 0x3F) == 0x3F {
             // Arbitrary tag follows
             reader.read_tag().map_err(|_| Woff2Error::InvalidTableDirectory)?
         } else {
-            let idx = (flags & 0x3F) as usize;
-            if idx >= KNOWN_TAGS.len() {
-                return Err(Woff2Error::InvalidTableDirectory);
-            }
-            KNOWN_TAGS[idx]
+            KNOWN_TAGS[(flags & 0x3F) as usize]
         };
-        
+
         let orig_length = read_uint_base128(reader)?;
-        
-        // Transform length only for tables with transformation
-        let transform_length = if requires_transform(&tag) || (flags & 0x40) != 0 {
-            // Read transform length if different from orig_length
-            let tf_len = read_uint_base128(reader)?;
-            Some(tf_len)
-        } else {
-            None
+
+        // Only glyf/loca (null transform = 3) and hmtx (transform = 1) have versions
+        let version = flags >> 6;
+        let valid_version = match &tag {
+            b"glyf" | b"loca" => version == 0 || version == 3,
+            _ if requires_transform(&tag) => version <= 1,
+            _ => version == 0,
         };
-        
-        entries.push(Woff2TableEntry {
+        if !valid_version {
+            return Err(Woff2Error::InvalidTableDirectory);
+        }
+
+        let mut entry = Woff2TableEntry {
             tag,
             orig_length,
-            transform_length,
+            transform_length: None,
             flags,
-        });
+        };
+        if entry.has_transform() {
+            let transform_length = read_uint_base128(reader)?;
+            // Transformed loca is rebuilt from glyf and carries no data
+            if tag == *b"loca" && transform_length != 0 {
+                return Err(Woff2Error::InvalidTableDirectory);
+            }
+            entry.transform_length = Some(transform_length);
+        }
+
+        entries.push(entry);
     }
-    
+
     Ok(entries)
 }
 
+/// sfnt version of font collections ('ttcf')
+const TTC_FLAVOR: u32 = 0x74746366;
+
+/// A font inside the WOFF2 file: its flavor and the tables it references
+#[derive(Debug, Clone)]
+struct Woff2FontEntry {
+    flavor: u32,
+    /// Indices into the table directory
+    tables: Vec<usize>,
+}
+
+/// Parse the collection directory that follows the table directory in TTC flavored files
+fn parse_collection_directory(
+    reader: &mut FontReader,
+    num_tables: usize,
+) -> Woff2Result<(u32, Vec<Woff2FontEntry>)> {
+    let version = reader.read_u32().map_err(|_| Woff2Error::InvalidTableDirectory)?;
+    let num_fonts = read_255_uint16(reader)?;
+    if num_fonts == 0 {
+        return Err(Woff2Error::InvalidTableDirectory);
+    }
+
+    let mut fonts = Vec::with_capacity(num_fonts as usize);
+    for _ in 0..num_fonts {
+        let font_tables = read_255_uint16(reader)?;
+        let flavor = reader.read_u32().map_err(|_| Woff2Error::InvalidTableDirectory)?;
+        let mut tables = Vec::with_capacity(font_tables as usize);
+        for _ in 0..font_tables {
+            let index = read_255_uint16(reader)? as usize;
+            if index >= num_tables {
+                return Err(Woff2Error::InvalidTableDirectory);
+            }
+            tables.push(index);
+        }
+        fonts.push(Woff2FontEntry { flavor, tables });
+    }
+
+    Ok((version, fonts))
+}
+
 /// Check if data is WOFF2 format
 pub fn is_woff2(data: &[u8]) -> bool {
-    data.len() >= 4 && 
+    data.len() >= 4 &&
     u32::from_be_bytes([data[0], data[1], data[2], data[3]]) == WOFF2_SIGNATURE
 }
 
-/// Decode WOFF2 to raw OpenType/TrueType data
+/// Decode WOFF2 to raw OpenType/TrueType data.
+///
+/// Collections decode to a TrueType Collection ('ttcf') sharing tables
+/// between faces, like the original font file.
 pub fn decode_woff2(data: &[u8]) -> Option<Vec<u8>> {
     decode_woff2_inner(data).ok()
 }
 
 /// Internal WOFF2 decoder with detailed error handling
 fn decode_woff2_inner(data: &[u8]) -> Woff2Result<Vec<u8>> {
+    let decoded = decode_tables(data)?;
+    build_font_file(decoded.collection_version, &decoded.fonts, &decoded.tables)
+}
+
+/// Tables of a WOFF2 file after decompression and inverse transforms
+struct DecodedWoff2 {
+    /// Collection header version, `None` for single fonts
+    collection_version: Option<u32>,
+    fonts: Vec<Woff2FontEntry>,
+    /// Tables in directory order
+    tables: Vec<ProcessedTable>,
+}
+
+/// Parse the container, decompress the table data and undo transforms
+fn decode_tables(data: &[u8]) -> Woff2Result<DecodedWoff2> {
     let mut reader = FontReader::new(data);
-    
+
     // Parse header
     let header = Woff2Header::parse(&mut reader)?;
-    
+    if header.length as usize > data.len() || header.num_tables == 0 {
+        return Err(Woff2Error::InvalidHeader);
+    }
+
     // Parse table directory
     let entries = parse_table_directory(&mut reader, header.num_tables)?;
-    
+
+    let (collection_version, fonts) = if header.flavor == TTC_FLAVOR {
+        let (version, fonts) = parse_collection_directory(&mut reader, entries.len())?;
+        (Some(version), fonts)
+    } else {
+        let font = Woff2FontEntry {
+            flavor: header.flavor,
+            tables: (0..entries.len()).collect(),
+        };
+        (None, vec![font])
+    };
+
     // Get compressed data
     let compressed_start = reader.pos();
     let compressed_end = compressed_start + header.total_compressed_size as usize;
-    
+
     if compressed_end > data.len() {
         return Err(Woff2Error::InvalidData);
     }
-    
+
     let compressed_data = &data[compressed_start..compressed_end];
-    
-    // Decompress using Brotli
-    let decompressed = decompress_with_dict(compressed_data, WOFF2_DICTIONARY)?;
-    
+
+    // All tables are compressed as a single Brotli stream
+    let expected_size: usize = entries.iter().map(|e| e.stored_length() as usize).sum();
+    let decompressed = BrotliDecoder::new()
+        .with_output_limit(expected_size)
+        .decompress(compressed_data)?;
+    if decompressed.len() != expected_size {
+        return Err(Woff2Error::InvalidData);
+    }
+
     // Process tables and apply inverse transforms
-    let mut tables = process_tables(&entries, &decompressed, &header)?;
-    
-    // Reconstruct OpenType container
-    let sfnt = reconstruct_sfnt(&header, &entries, &mut tables)?;
-    
-    Ok(sfnt)
+    let tables = process_tables(&entries, &fonts, &decompressed)?;
+
+    Ok(DecodedWoff2 {
+        collection_version,
+        fonts,
+        tables,
+    })
 }
 
 /// Processed table data
@@ -286,169 +375,199 @@ struct ProcessedTable {
 /// Process tables, applying inverse transforms where needed
 fn process_tables(
     entries: &[Woff2TableEntry],
+    fonts: &[Woff2FontEntry],
     decompressed: &[u8],
-    header: &Woff2Header,
 ) -> Woff2Result<Vec<ProcessedTable>> {
-    let mut tables = Vec::with_capacity(entries.len());
+    // First pass: locate each table's bytes in the decompressed stream
+    let mut raw_tables = Vec::with_capacity(entries.len());
     let mut offset = 0usize;
-    
-    // First pass: extract all table data
-    let mut raw_tables: Vec<(&Woff2TableEntry, Vec<u8>)> = Vec::new();
-    
     for entry in entries {
-        let length = entry.transform_length.unwrap_or(entry.orig_length) as usize;
-        
-        if offset + length > decompressed.len() {
-            return Err(Woff2Error::InvalidData);
-        }
-        
-        let table_data = decompressed[offset..offset + length].to_vec();
-        raw_tables.push((entry, table_data));
+        let length = entry.stored_length() as usize;
+        raw_tables.push(&decompressed[offset..offset + length]);
         offset += length;
     }
-    
-    // Get num_glyphs from maxp table
-    let num_glyphs = get_num_glyphs(&raw_tables)?;
-    
-    // Get num_h_metrics from hhea table
-    let num_h_metrics = get_num_h_metrics(&raw_tables)?;
-    
-    // Second pass: apply transforms
-    let mut glyf_data: Option<Vec<u8>> = None;
-    let mut loca_offsets: Option<Vec<u32>> = None;
-    
-    // Find and process glyf first (loca depends on it)
-    for (entry, data) in &raw_tables {
-        if entry.tag == *b"glyf" && entry.has_transform() {
-            let (glyf, offsets) = reconstruct_glyf(data, num_glyphs)?;
-            glyf_data = Some(glyf);
-            loca_offsets = Some(offsets);
-            break;
-        }
-    }
-    
-    for (entry, data) in raw_tables {
-        let processed_data = if entry.has_transform() {
-            match &entry.tag {
-                b"glyf" => {
-                    // Already processed above
-                    glyf_data.take().ok_or(Woff2Error::InvalidData)?
-                }
-                b"loca" => {
-                    // Generate from glyf offsets
-                    let offsets = loca_offsets.as_ref()
-                        .ok_or(Woff2Error::TableNotFound("glyf"))?;
-                    let use_short = can_use_short_loca(offsets);
-                    generate_loca(offsets, use_short)
-                }
-                b"hmtx" => {
-                    reconstruct_hmtx(&data, num_glyphs, num_h_metrics)?
+
+    // Second pass: undo transforms font by font (collections share tables)
+    let mut processed: Vec<Option<Vec<u8>>> = vec![None; entries.len()];
+    let mut x_mins: Vec<Option<Vec<i16>>> = vec![None; entries.len()];
+
+    for font in fonts {
+        let find = |tag: &[u8; 4]| font.tables.iter().copied().find(|&i| entries[i].tag == *tag);
+
+        // Find and process glyf first (loca and hmtx depend on it)
+        let glyf = find(b"glyf");
+        if let Some(glyf) = glyf.filter(|&i| entries[i].has_transform()) {
+            let loca = find(b"loca").ok_or(Woff2Error::TableNotFound("loca"))?;
+            if !entries[loca].has_transform() {
+                return Err(Woff2Error::InvalidTableDirectory);
+            }
+            if processed[glyf].is_none() {
+                let reconstructed = reconstruct_glyf(raw_tables[glyf])?;
+                if reconstructed.loca.len() != entries[loca].orig_length as usize {
+                    return Err(Woff2Error::InvalidData);
                 }
-                _ => {
-                    // No transform, use as-is
-                    data
+                processed[glyf] = Some(reconstructed.glyf);
+                processed[loca] = Some(reconstructed.loca);
+                x_mins[glyf] = Some(reconstructed.x_mins);
+
+                // Keep head.indexToLocFormat consistent with the rebuilt loca
+                let head = find(b"head").ok_or(Woff2Error::TableNotFound("head"))?;
+                let mut head_data = processed[head].take().unwrap_or_else(|| raw_tables[head].to_vec());
+                if head_data.len() < 54 {
+                    return Err(Woff2Error::InvalidData);
                 }
+                head_data[50..52].copy_from_slice(&reconstructed.index_format.to_be_bytes());
+                processed[head] = Some(head_data);
             }
-        } else {
-            // Untransformed, use directly
-            data
+        }
+
+        if let Some(hmtx) = find(b"hmtx").filter(|&i| entries[i].has_transform())
+            && processed[hmtx].is_none()
+        {
+            let glyph_x_mins = glyf
+                .and_then(|glyf| x_mins[glyf].as_deref())
+                .ok_or(Woff2Error::TableNotFound("glyf"))?;
+            let num_glyphs = get_num_glyphs(find(b"maxp").map(|i| raw_tables[i]))?;
+            let num_h_metrics = get_num_h_metrics(find(b"hhea").map(|i| raw_tables[i]))?;
+            processed[hmtx] = Some(reconstruct_hmtx(
+                raw_tables[hmtx],
+                num_glyphs,
+                num_h_metrics,
+                glyph_x_mins,
+            )?);
+        }
+    }
+
+    let mut tables = Vec::with_capacity(entries.len());
+    for (i, entry) in entries.iter().enumerate() {
+        let data = match processed[i].take() {
+            Some(data) => data,
+            // A transformed table nobody could rebuild (e.g. loca without glyf)
+            None if entry.has_transform() => return Err(Woff2Error::InvalidTableDirectory),
+            None => raw_tables[i].to_vec(),
         };
-        
-        tables.push(ProcessedTable {
-            tag: entry.tag,
-            data: processed_data,
-        });
+        tables.push(ProcessedTable { tag: entry.tag, data });
     }
-    
+
     Ok(tables)
 }
 
 /// Get number of glyphs from maxp table
-fn get_num_glyphs(tables: &[(&Woff2TableEntry, Vec<u8>)]) -> Woff2Result<u16> {
-    for (entry, data) in tables {
-        if entry.tag == *b"maxp" && data.len() >= 6 {
-            return Ok(u16::from_be_bytes([data[4], data[5]]));
-        }
+fn get_num_glyphs(maxp: Option<&[u8]>) -> Woff2Result<u16> {
+    match maxp {
+        Some(data) if data.len() >= 6 => Ok(u16::from_be_bytes([data[4], data[5]])),
+        _ => Err(Woff2Error::TableNotFound("maxp")),
     }
-    Err(Woff2Error::TableNotFound("maxp"))
 }
 
 /// Get number of horizontal metrics from hhea table
-fn get_num_h_metrics(tables: &[(&Woff2TableEntry, Vec<u8>)]) -> Woff2Result<u16> {
-    for (entry, data) in tables {
-        if entry.tag == *b"hhea" && data.len() >= 36 {
-            return Ok(u16::from_be_bytes([data[34], data[35]]));
-        }
+fn get_num_h_metrics(hhea: Option<&[u8]>) -> Woff2Result<u16> {
+    match hhea {
+        Some(data) if data.len() >= 36 => Ok(u16::from_be_bytes([data[34], data[35]])),
+        _ => Err(Woff2Error::TableNotFound("hhea")),
     }
-    Err(Woff2Error::TableNotFound("hhea"))
 }
 
-/// Reconstruct the OpenType sfnt container
-fn reconstruct_sfnt(
-    header: &Woff2Header,
-    entries: &[Woff2TableEntry],
-    tables: &mut [ProcessedTable],
+/// Size of an sfnt offset table with `num_tables` entries
+fn offset_table_size(num_tables: usize) -> usize {
+    12 + num_tables * 16
+}
+
+/// Reconstruct an sfnt font, or a TrueType Collection when `collection_version` is set
+fn build_font_file(
+    collection_version: Option<u32>,
+    fonts: &[Woff2FontEntry],
+    tables: &[ProcessedTable],
 ) -> Woff2Result<Vec<u8>> {
-    let num_tables = tables.len() as u16;
-    
-    // Calculate sfnt header size
-    let header_size = 12 + num_tables as usize * 16;
-    
-    // Calculate total size
-    let mut total_size = header_size;
-    for table in tables.iter() {
-        total_size += table.data.len();
-        // Align to 4 bytes
-        total_size = (total_size + 3) & !3;
-    }
-    
-    let mut output = vec![0u8; total_size];
-    
-    // Write sfnt header
-    output[0..4].copy_from_slice(&header.flavor.to_be_bytes());
-    output[4..6].copy_from_slice(&num_tables.to_be_bytes());
-    
-    // Calculate search params
-    let entry_selector = (num_tables as f32).log2().floor() as u16;
-    let search_range = (1u16 << entry_selector) * 16;
-    let range_shift = num_tables.saturating_mul(16).saturating_sub(search_range);
-    
-    output[6..8].copy_from_slice(&search_range.to_be_bytes());
-    output[8..10].copy_from_slice(&entry_selector.to_be_bytes());
-    output[10..12].copy_from_slice(&range_shift.to_be_bytes());
-    
-    // Write table directory and data
-    let mut table_offset = header_size;
-    
-    for (i, table) in tables.iter().enumerate() {
-        let dir_entry_offset = 12 + i * 16;
-        
-        // Tag
-        output[dir_entry_offset..dir_entry_offset + 4].copy_from_slice(&table.tag);
-        
-        // Checksum (calculate)
-        let checksum = calculate_checksum(&table.data);
-        output[dir_entry_offset + 4..dir_entry_offset + 8].copy_from_slice(&checksum.to_be_bytes());
-        
-        // Offset
-        output[dir_entry_offset + 8..dir_entry_offset + 12]
-            .copy_from_slice(&(table_offset as u32).to_be_bytes());
-        
-        // Length
-        output[dir_entry_offset + 12..dir_entry_offset + 16]
-            .copy_from_slice(&(table.data.len() as u32).to_be_bytes());
-        
-        // Copy table data
-        output[table_offset..table_offset + table.data.len()].copy_from_slice(&table.data);
-        
-        // Advance with alignment
-        table_offset += table.data.len();
-        table_offset = (table_offset + 3) & !3;
-    }
-    
-    // Update head table checksum adjustment
-    update_head_checksum(&mut output, num_tables)?;
-    
+    // TTC header: tag, version, numFonts, offsets (+ empty DSIG fields in 2.0)
+    let ttc_header_size = match collection_version {
+        Some(version) => 12 + fonts.len() * 4 + if version >= 0x00020000 { 12 } else { 0 },
+        None => 0,
+    };
+
+    let mut font_offsets = Vec::with_capacity(fonts.len());
+    let mut table_offset = ttc_header_size;
+    for font in fonts {
+        font_offsets.push(table_offset);
+        table_offset += offset_table_size(font.tables.len());
+    }
+
+    // checksumAdjustment is computed once everything is in place
+    let mut table_offsets = Vec::with_capacity(tables.len());
+    let mut checksums = Vec::with_capacity(tables.len());
+    for table in tables {
+        table_offsets.push(table_offset);
+        table_offset = (table_offset + table.data.len() + 3) & !3;
+
+        let mut checksum = calculate_checksum(&table.data);
+        if table.tag == *b"head" && table.data.len() >= 12 {
+            let adjustment = u32::from_be_bytes([table.data[8], table.data[9], table.data[10], table.data[11]]);
+            checksum = checksum.wrapping_sub(adjustment);
+        }
+        checksums.push(checksum);
+    }
+
+    let mut output = vec![0u8; table_offset];
+
+    if let Some(version) = collection_version {
+        output[0..4].copy_from_slice(&TTC_FLAVOR.to_be_bytes());
+        output[4..8].copy_from_slice(&version.to_be_bytes());
+        output[8..12].copy_from_slice(&(fonts.len() as u32).to_be_bytes());
+        for (i, &offset) in font_offsets.iter().enumerate() {
+            output[12 + i * 4..16 + i * 4].copy_from_slice(&(offset as u32).to_be_bytes());
+        }
+    }
+
+    for (font, &font_offset) in fonts.iter().zip(&font_offsets) {
+        // Directory entries must be sorted by tag
+        let mut indices = font.tables.clone();
+        indices.sort_by_key(|&i| tables[i].tag);
+
+        let num_tables = indices.len() as u16;
+        let entry_selector = 15 - num_tables.max(1).leading_zeros() as u16;
+        let search_range = (1u16 << entry_selector) * 16;
+        let range_shift = num_tables * 16 - search_range;
+
+        let header = &mut output[font_offset..font_offset + 12];
+        header[0..4].copy_from_slice(&font.flavor.to_be_bytes());
+        header[4..6].copy_from_slice(&num_tables.to_be_bytes());
+        header[6..8].copy_from_slice(&search_range.to_be_bytes());
+        header[8..10].copy_from_slice(&entry_selector.to_be_bytes());
+        header[10..12].copy_from_slice(&range_shift.to_be_bytes());
+
+        for (slot, &i) in indices.iter().enumerate() {
+            let entry = &mut output[font_offset + 12 + slot * 16..font_offset + 28 + slot * 16];
+            entry[0..4].copy_from_slice(&tables[i].tag);
+            entry[4..8].copy_from_slice(&checksums[i].to_be_bytes());
+            entry[8..12].copy_from_slice(&(table_offsets[i] as u32).to_be_bytes());
+            entry[12..16].copy_from_slice(&(tables[i].data.len() as u32).to_be_bytes());
+        }
+    }
+
+    for (table, &offset) in tables.iter().zip(&table_offsets) {
+        output[offset..offset + table.data.len()].copy_from_slice(&table.data);
+    }
+
+    // Update head table checksum adjustment of every font
+    for (font, &font_offset) in fonts.iter().zip(&font_offsets) {
+        let head = font
+            .tables
+            .iter()
+            .copied()
+            .find(|&i| tables[i].tag == *b"head" && tables[i].data.len() >= 12)
+            .ok_or(Woff2Error::TableNotFound("head"))?;
+
+        let directory = &output[font_offset..font_offset + offset_table_size(font.tables.len())];
+        let font_checksum = font
+            .tables
+            .iter()
+            .fold(calculate_checksum(directory), |sum, &i| sum.wrapping_add(checksums[i]));
+
+        let adjustment = 0xB1B0AFBA_u32.wrapping_sub(font_checksum);
+        let offset = table_offsets[head] + 8;
+        output[offset..offset + 4].copy_from_slice(&adjustment.to_be_bytes());
+    }
+
     Ok(output)
 }
 
@@ -456,7 +575,7 @@ fn reconstruct_sfnt(
 fn calculate_checksum(data: &[u8]) -> u32 {
     let mut sum = 0u32;
     let padded_len = (data.len() + 3) & !3;
-    
+
     for i in (0..padded_len).step_by(4) {
         let word = if i + 4 <= data.len() {
             u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]])
@@ -472,52 +591,38 @@ fn calculate_checksum(data: &[u8]) -> u32 {
         };
         sum = sum.wrapping_add(word);
     }
-    
-    sum
-}
 
-/// Update checksumAdjustment in head table
-fn update_head_checksum(sfnt: &mut [u8], num_tables: u16) -> Woff2Result<()> {
-    // Find head table
-    for i in 0..num_tables as usize {
-        let dir_offset = 12 + i * 16;
-        let tag = &sfnt[dir_offset..dir_offset + 4];
-        
-        if tag == b"head" {
-            let table_offset = u32::from_be_bytes([
-                sfnt[dir_offset + 8],
-                sfnt[dir_offset + 9],
-                sfnt[dir_offset + 10],
-                sfnt[dir_offset + 11],
-            ]) as usize;
-            
-            // Clear existing checksum adjustment (offset 8 in head table)
-            sfnt[table_offset + 8..table_offset + 12].copy_from_slice(&[0, 0, 0, 0]);
-            
-            // Calculate whole-file checksum
-            let file_checksum = calculate_checksum(sfnt);
-            
-            // Set adjustment
-            let adjustment = 0xB1B0AFBA_u32.wrapping_sub(file_checksum);
-            sfnt[table_offset + 8..table_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
-            
-            return Ok(());
-        }
-    }
-    
-    Err(Woff2Error::TableNotFound("head"))
+    sum
 }
 
 // ============================================================================
 // Collection (TTC) Support
 // ============================================================================
 
-/// Decode WOFF2 with collection (TTC) support
+/// Decode WOFF2 with collection (TTC) support, returning one standalone
+/// sfnt per font (a single entry for non-collection files)
 pub fn decode_woff2_collection(data: &[u8]) -> Woff2Result<Vec<Vec<u8>>> {
-    // TODO: Implement TTC handling
-    // For now, treat as single font
-    let font = decode_woff2_inner(data)?;
-    Ok(vec![font])
+    let decoded = decode_tables(data)?;
+
+    decoded
+        .fonts
+        .iter()
+        .map(|font| {
+            let tables: Vec<ProcessedTable> = font
+                .tables
+                .iter()
+                .map(|&i| ProcessedTable {
+                    tag: decoded.tables[i].tag,
+                    data: decoded.tables[i].data.clone(),
+                })
+                .collect();
+            let single = Woff2FontEntry {
+                flavor: font.flavor,
+                tables: (0..tables.len()).collect(),
+            };
+            build_font_file(None, &[single], &tables)
+        })
+        .collect()
 }
 
 // ============================================================================
@@ -569,13 +674,18 @@ mod tests {
         let mut reader = FontReader::new(&data);
         assert_eq!(read_255_uint16(&mut reader).unwrap(), 100);
 
-        // 253 + second
-        let data = [253, 50];
+        // 255 prefix: 253 + next byte
+        let data = [255, 50];
         let mut reader = FontReader::new(&data);
         assert_eq!(read_255_uint16(&mut reader).unwrap(), 303);
 
-        // 255 prefix for full 16-bit
-        let data = [255, 0x10, 0x00];
+        // 254 prefix: 506 + next byte
+        let data = [254, 50];
+        let mut reader = FontReader::new(&data);
+        assert_eq!(read_255_uint16(&mut reader).unwrap(), 556);
+
+        // 253 prefix for full 16-bit
+        let data = [253, 0x10, 0x00];
         let mut reader = FontReader::new(&data);
         assert_eq!(read_255_uint16(&mut reader).unwrap(), 0x1000);
     }
@@ -590,6 +700,90 @@ mod tests {
         assert_eq!(calculate_checksum(&data), 1 + 0xFF000000);
     }
 
+    /// Wrap data in a Brotli stream made of one uncompressed meta-block
+    fn brotli_stored(data: &[u8]) -> Vec<u8> {
+        // WBITS=16, MNIBBLES=4, MLEN-1, ISUNCOMPRESSED
+        let header = (((data.len() - 1) as u32) << 4) | (1 << 20);
+        let mut stream = header.to_le_bytes()[..3].to_vec();
+        stream.extend_from_slice(data);
+        // Final empty meta-block
+        stream.push(0x03);
+        stream
+    }
+
+    /// Find a table in a reconstructed sfnt
+    fn sfnt_table<'a>(sfnt: &'a [u8], tag: &[u8; 4]) -> Option<&'a [u8]> {
+        let num_tables = u16::from_be_bytes([sfnt[4], sfnt[5]]) as usize;
+        (0..num_tables).map(|i| &sfnt[12 + i * 16..28 + i * 16]).find(|e| &e[0..4] == tag).map(|e| {
+            let offset = u32::from_be_bytes([e[8], e[9], e[10], e[11]]) as usize;
+            let length = u32::from_be_bytes([e[12], e[13], e[14], e[15]]) as usize;
+            &sfnt[offset..offset + length]
+        })
+    }
+
+    #[test]
+    fn test_decode_transformed_glyf() {
+        // Transformed glyf stream holding a single triangle
+        let mut glyf = vec![0, 0, 0, 0, 0, 1, 0, 0];
+        for size in [2u32, 1, 3, 5, 0, 4, 0] {
+            glyf.extend_from_slice(&size.to_be_bytes());
+        }
+        glyf.extend_from_slice(&[0, 1, 3, 1, 11, 86, 0, 100, 49, 99, 0, 0, 0, 0, 0]);
+
+        let mut head = vec![0u8; 54];
+        head[51] = 1; // Long loca, but the glyf stream asks for short offsets
+        let maxp = vec![0, 0, 0x50, 0, 0, 1];
+        let mut hhea = vec![0u8; 36];
+        hhea[35] = 1;
+        let hmtx = vec![0x02, 0x00, 0, 0];
+
+        // (flags, origLength, transformLength); flags hold the known tag index
+        let directory: [(u8, u8, Option<u8>); 6] = [
+            (10, 20, Some(glyf.len() as u8)), // glyf
+            (11, 4, Some(0)),                 // loca
+            (1, 54, None),                    // head
+            (2, 36, None),                    // hhea
+            (4, 6, None),                     // maxp
+            (3, 4, None),                     // hmtx
+        ];
+        let stored = [glyf, head, hhea, maxp, hmtx].concat();
+        let compressed = brotli_stored(&stored);
+
+        let mut table_directory = Vec::new();
+        for (flags, orig_length, transform_length) in directory {
+            table_directory.extend_from_slice(&[flags, orig_length]);
+            table_directory.extend(transform_length);
+        }
+
+        let length = 48 + table_directory.len() + compressed.len();
+        let mut woff2 = Vec::new();
+        woff2.extend_from_slice(b"wOF2");
+        woff2.extend_from_slice(&0x00010000u32.to_be_bytes());
+        woff2.extend_from_slice(&(length as u32).to_be_bytes());
+        woff2.extend_from_slice(&6u16.to_be_bytes());
+        woff2.extend_from_slice(&[0; 2]);
+        woff2.extend_from_slice(&0u32.to_be_bytes());
+        woff2.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        woff2.extend_from_slice(&[0; 24]);
+        woff2.extend_from_slice(&table_directory);
+        woff2.extend_from_slice(&compressed);
+
+        let sfnt = decode_woff2(&woff2).unwrap();
+
+        // Directory is sorted by tag
+        let tags: Vec<&[u8]> = (0..6).map(|i| &sfnt[12 + i * 16..16 + i * 16]).collect();
+        assert_eq!(tags, [b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp"]);
+
+        assert_eq!(sfnt_table(&sfnt, b"glyf").unwrap().len(), 20);
+        assert_eq!(sfnt_table(&sfnt, b"loca").unwrap(), [0, 0, 0, 10]);
+        // indexToLocFormat follows the rebuilt loca
+        assert_eq!(&sfnt_table(&sfnt, b"head").unwrap()[50..52], [0, 0]);
+        // checksumAdjustment makes the whole font sum to the magic value
+        assert_eq!(calculate_checksum(&sfnt), 0xB1B0AFBA);
+
+        assert_eq!(decode_woff2_collection(&woff2).unwrap(), vec![sfnt]);
+    }
+
     #[test]
     fn test_woff2_header_size() {
         // Header should be 44 bytes
@@ -3,7 +3,7 @@
 //! Implements the table-specific transformations used by WOFF2:
 //! - glyf: Triplet encoding for point coordinates
 //! - loca: Reconstructed from glyf offsets
-//! - hmtx: Delta encoding for metrics
+//! - hmtx: Side bearings recovered from glyph bounding boxes
 
 use super::parser::reader::FontReader;
 
@@ -42,326 +42,194 @@ pub type TransformResult<T> = Result<T, TransformError>;
 /// Glyf table transform header
 #[derive(Debug)]
 struct GlyfTransformHeader {
+    /// Bit 0: an overlapSimpleBitmap follows the streams
+    option_flags: u16,
     /// Number of glyphs
     num_glyphs: u16,
-    /// Offset to nContour stream
-    n_contour_offset: u32,
-    /// Offset to nPoints stream
-    n_points_offset: u32,
-    /// Offset to flag stream
-    flag_offset: u32,
-    /// Offset to glyph stream (instructions, composite data)
-    glyph_offset: u32,
-    /// Offset to composite stream
-    composite_offset: u32,
-    /// Offset to bbox bitmap
-    bbox_bitmap_offset: u32,
-    /// Offset to bbox stream
-    bbox_offset: u32,
-    /// Offset to instruction stream
-    instruction_offset: u32,
+    /// loca format of the reconstructed font (0 = short, 1 = long)
+    index_format: u16,
+    /// Size of the nContour stream
+    n_contour_size: u32,
+    /// Size of the nPoints stream
+    n_points_size: u32,
+    /// Size of the flag stream
+    flag_size: u32,
+    /// Size of the glyph stream (triplets, instruction lengths)
+    glyph_size: u32,
+    /// Size of the composite stream
+    composite_size: u32,
+    /// Size of the bbox stream (bitmap followed by boxes)
+    bbox_size: u32,
+    /// Size of the instruction stream
+    instruction_size: u32,
 }
 
-/// Triplet encoding lookup tables
-/// Point delta values encoded as variable-length bytes
-const TRIPLET_BYTE_COUNT: [u8; 128] = triplet_byte_count_table();
-const TRIPLET_X_BITS: [u8; 128] = triplet_x_bits_table();
-const TRIPLET_Y_BITS: [u8; 128] = triplet_y_bits_table();
-
-const fn triplet_byte_count_table() -> [u8; 128] {
-    let mut table = [0u8; 128];
-    let mut i = 0;
-    while i < 128 {
-        table[i] = match i {
-            0..=9 => 1,
-            10..=57 => 2,
-            58..=83 => 3,
-            84..=119 => 4,
-            _ => 5,
-        };
-        i += 1;
+impl GlyfTransformHeader {
+    fn parse(reader: &mut FontReader) -> TransformResult<Self> {
+        let _reserved = reader.read_u16().map_err(|_| TransformError::InvalidData)?;
+        Ok(Self {
+            option_flags: reader.read_u16().map_err(|_| TransformError::InvalidData)?,
+            num_glyphs: reader.read_u16().map_err(|_| TransformError::InvalidData)?,
+            index_format: reader.read_u16().map_err(|_| TransformError::InvalidData)?,
+            n_contour_size: reader.read_u32().map_err(|_| TransformError::InvalidData)?,
+            n_points_size: reader.read_u32().map_err(|_| TransformError::InvalidData)?,
+            flag_size: reader.read_u32().map_err(|_| TransformError::InvalidData)?,
+            glyph_size: reader.read_u32().map_err(|_| TransformError::InvalidData)?,
+            composite_size: reader.read_u32().map_err(|_| TransformError::InvalidData)?,
+            bbox_size: reader.read_u32().map_err(|_| TransformError::InvalidData)?,
+            instruction_size: reader.read_u32().map_err(|_| TransformError::InvalidData)?,
+        })
     }
-    table
 }
 
-const fn triplet_x_bits_table() -> [u8; 128] {
-    let mut table = [0u8; 128];
-    let mut i = 0;
-    while i < 128 {
-        table[i] = match i {
-            0..=3 => 0,
-            4..=9 => 0,
-            10..=19 => 8,
-            20..=35 => 0,
-            36..=57 => 8,
-            58..=67 => 8,
-            68..=83 => 16,
-            84..=95 => 8,
-            96..=119 => 16,
-            _ => 16,
-        };
-        i += 1;
-    }
-    table
-}
-
-const fn triplet_y_bits_table() -> [u8; 128] {
-    let mut table = [0u8; 128];
-    let mut i = 0;
-    while i < 128 {
-        table[i] = match i {
-            0..=3 => 0,
-            4..=9 => 4,
-            10..=19 => 0,
-            20..=35 => 8,
-            36..=57 => 8,
-            58..=67 => 16,
-            68..=83 => 8,
-            84..=95 => 16,
-            96..=119 => 16,
-            _ => 16,
-        };
-        i += 1;
-    }
-    table
-}
+/// Decode one point triplet (flag from the flag stream, data from the glyph stream).
+///
+/// Returns (dx, dy, on_curve).
+fn decode_triplet(flag: u8, reader: &mut StreamReader) -> TransformResult<(i32, i32, bool)> {
+    let on_curve = flag & 0x80 == 0;
+    let flag = (flag & 0x7F) as i32;
+
+    // The low bits of the flag select the signs of dx and dy
+    let with_sign = |f: i32, value: i32| if f & 1 != 0 { value } else { -value };
+
+    let (dx, dy) = if flag < 10 {
+        let b0 = reader.read_u8()? as i32;
+        (0, with_sign(flag, ((flag & 14) << 7) + b0))
+    } else if flag < 20 {
+        let b0 = reader.read_u8()? as i32;
+        (with_sign(flag, (((flag - 10) & 14) << 7) + b0), 0)
+    } else if flag < 84 {
+        let b0 = flag - 20;
+        let b1 = reader.read_u8()? as i32;
+        (
+            with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+            with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (b1 & 0x0F)),
+        )
+    } else if flag < 120 {
+        let b0 = flag - 84;
+        let b1 = reader.read_u8()? as i32;
+        let b2 = reader.read_u8()? as i32;
+        (
+            with_sign(flag, 1 + ((b0 / 12) << 8) + b1),
+            with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2),
+        )
+    } else if flag < 124 {
+        let b1 = reader.read_u8()? as i32;
+        let b2 = reader.read_u8()? as i32;
+        let b3 = reader.read_u8()? as i32;
+        (
+            with_sign(flag, (b1 << 4) + (b2 >> 4)),
+            with_sign(flag >> 1, ((b2 & 0x0F) << 8) + b3),
+        )
+    } else {
+        let x = reader.read_u16()? as i32;
+        let y = reader.read_u16()? as i32;
+        (with_sign(flag, x), with_sign(flag >> 1, y))
+    };
 
-/// Triplet decoder for glyf coordinates
-struct TripletDecoder<'a> {
-    data: &'a [u8],
-    pos: usize,
+    Ok((dx, dy, on_curve))
 }
 
-impl<'a> TripletDecoder<'a> {
-    fn new(data: &'a [u8]) -> Self {
-        Self { data, pos: 0 }
-    }
-
-    /// Decode a single triplet, returning (dx, dy, on_curve)
-    fn decode_triplet(&mut self, flag: u8) -> TransformResult<(i16, i16, bool)> {
-        let flag_byte = flag & 0x7F;
-        let on_curve = (flag & 0x80) == 0;
-
-        if flag_byte as usize >= TRIPLET_BYTE_COUNT.len() {
-            return Err(TransformError::InvalidTriplet);
-        }
-
-        let byte_count = TRIPLET_BYTE_COUNT[flag_byte as usize];
-        let x_bits = TRIPLET_X_BITS[flag_byte as usize];
-        let y_bits = TRIPLET_Y_BITS[flag_byte as usize];
-
-        if self.pos + byte_count as usize > self.data.len() {
-            return Err(TransformError::UnexpectedEof);
-        }
-
-        let (dx, dy) = self.decode_delta(flag_byte, x_bits, y_bits)?;
-
-        Ok((dx, dy, on_curve))
-    }
-
-    fn decode_delta(&mut self, flag: u8, x_bits: u8, y_bits: u8) -> TransformResult<(i16, i16)> {
-        // Decode based on the flag patterns
-        let (dx, dy) = match flag {
-            // One-byte deltas (dx=0 or dy=0 with small values)
-            0..=3 => {
-                // Both zero
-                (0, 0)
-            }
-            4..=9 => {
-                // small y delta only
-                let y = self.read_delta(4, (flag - 4) & 1 != 0)?;
-                (0, y)
-            }
-            10..=19 => {
-                // x only, 8 bits
-                let x = self.read_u8()? as i16;
-                let sign = (flag - 10) & 1 != 0;
-                (if sign { -x } else { x }, 0)
-            }
-            20..=35 => {
-                // y only, 8 bits
-                let y = self.read_u8()? as i16;
-                let sign = (flag - 20) & 1 != 0;
-                (0, if sign { -y } else { y })
-            }
-            36..=57 => {
-                // x 8 bits, y 8 bits, various signs
-                let x = self.read_u8()? as i16;
-                let y = self.read_u8()? as i16;
-                let idx = flag - 36;
-                let x_sign = (idx >> 1) & 1 != 0;
-                let y_sign = idx & 1 != 0;
-                (if x_sign { -x } else { x }, if y_sign { -y } else { y })
-            }
-            58..=67 => {
-                // x 8 bits, y 16 bits
-                let x = self.read_u8()? as i16;
-                let y = self.read_u16()? as i16;
-                let idx = flag - 58;
-                let x_sign = (idx >> 1) & 1 != 0;
-                let y_sign = idx & 1 != 0;
-                (if x_sign { -x } else { x }, if y_sign { -y } else { y })
-            }
-            68..=83 => {
-                // x 16 bits, y 8 bits
-                let x = self.read_u16()? as i16;
-                let y = self.read_u8()? as i16;
-                let idx = flag - 68;
-                let x_sign = (idx >> 1) & 1 != 0;
-                let y_sign = idx & 1 != 0;
-                (if x_sign { -x } else { x }, if y_sign { -y } else { y })
-            }
-            84..=119 => {
-                // x and y with various bit widths
-                let x = self.read_u8()? as i16;
-                let y = self.read_u16()? as i16;
-                let idx = flag - 84;
-                let x_sign = (idx >> 1) & 1 != 0;
-                let y_sign = idx & 1 != 0;
-                (if x_sign { -x } else { x }, if y_sign { -y } else { y })
-            }
-            _ => {
-                // Full 16-bit coordinates
-                let x = self.read_i16()?;
-                let y = self.read_i16()?;
-                (x, y)
-            }
-        };
-
-        Ok((dx, dy))
-    }
-
-    fn read_delta(&mut self, bits: u8, negative: bool) -> TransformResult<i16> {
-        let val = match bits {
-            4 => (self.read_u8()? & 0x0F) as i16,
-            8 => self.read_u8()? as i16,
-            16 => self.read_u16()? as i16,
-            _ => 0,
-        };
-        Ok(if negative { -val } else { val })
-    }
-
-    fn read_u8(&mut self) -> TransformResult<u8> {
-        if self.pos >= self.data.len() {
-            return Err(TransformError::UnexpectedEof);
-        }
-        let v = self.data[self.pos];
-        self.pos += 1;
-        Ok(v)
-    }
-
-    fn read_u16(&mut self) -> TransformResult<u16> {
-        if self.pos + 2 > self.data.len() {
-            return Err(TransformError::UnexpectedEof);
-        }
-        let v = u16::from_be_bytes([self.data[self.pos], self.data[self.pos + 1]]);
-        self.pos += 2;
-        Ok(v)
-    }
-
-    fn read_i16(&mut self) -> TransformResult<i16> {
-        Ok(self.read_u16()? as i16)
-    }
+/// glyf and loca tables rebuilt from a transformed glyf stream
+#[derive(Debug)]
+pub struct GlyfReconstruction {
+    /// Reconstructed glyf table
+    pub glyf: Vec<u8>,
+    /// Matching loca table in the format given by the stream
+    pub loca: Vec<u8>,
+    /// Per-glyph xMin, used to rebuild hmtx side bearings
+    pub x_mins: Vec<i16>,
+    /// loca format (0 = short, 1 = long), mirrored into head.indexToLocFormat
+    pub index_format: u16,
 }
 
-/// Reconstruct glyf table from WOFF2 transformed data
-pub fn reconstruct_glyf(
-    transform_data: &[u8],
-    num_glyphs: u16,
-) -> TransformResult<(Vec<u8>, Vec<u32>)> {
-    if transform_data.is_empty() {
-        return Ok((Vec::new(), vec![0; num_glyphs as usize + 1]));
+/// Reconstruct glyf and loca tables from WOFF2 transformed data
+pub fn reconstruct_glyf(transform_data: &[u8]) -> TransformResult<GlyfReconstruction> {
+    let mut reader = FontReader::new(transform_data);
+    let header = GlyfTransformHeader::parse(&mut reader)?;
+    if header.index_format > 1 {
+        return Err(TransformError::InvalidData);
     }
+    let num_glyphs = header.num_glyphs as usize;
+
+    // Split the substreams that follow the header
+    let mut streams = StreamReader::new(&transform_data[reader.pos()..]);
+    let mut n_contour_reader = StreamReader::new(streams.read_bytes(header.n_contour_size as usize)?);
+    let mut n_points_reader = StreamReader::new(streams.read_bytes(header.n_points_size as usize)?);
+    let mut flag_reader = StreamReader::new(streams.read_bytes(header.flag_size as usize)?);
+    let mut glyph_reader = StreamReader::new(streams.read_bytes(header.glyph_size as usize)?);
+    let mut composite_reader = StreamReader::new(streams.read_bytes(header.composite_size as usize)?);
+    let mut bbox_reader = StreamReader::new(streams.read_bytes(header.bbox_size as usize)?);
+    let mut instruction_reader = StreamReader::new(streams.read_bytes(header.instruction_size as usize)?);
+    let overlap_bitmap = if header.option_flags & 1 != 0 {
+        Some(streams.read_bytes(num_glyphs.div_ceil(8))?)
+    } else {
+        None
+    };
 
-    let mut reader = FontReader::new(transform_data);
+    // The bbox stream starts with a bitmap of glyphs carrying explicit boxes
+    let bbox_bitmap = bbox_reader.read_bytes(num_glyphs.div_ceil(32) * 4)?;
+    let bit_set = |bitmap: &[u8], glyph: usize| bitmap[glyph / 8] & (0x80 >> (glyph % 8)) != 0;
 
-    // Read stream offsets
-    let _version = reader.read_u16().map_err(|_| TransformError::InvalidData)?;
-    let _options = reader.read_u16().map_err(|_| TransformError::InvalidData)?;
-    let n_contours_len = reader.read_u32().map_err(|_| TransformError::InvalidData)?;
-    let n_points_len = reader.read_u32().map_err(|_| TransformError::InvalidData)?;
-    let flag_len = reader.read_u32().map_err(|_| TransformError::InvalidData)?;
-    let glyph_len = reader.read_u32().map_err(|_| TransformError::InvalidData)?;
-    let composite_len = reader.read_u32().map_err(|_| TransformError::InvalidData)?;
-    let bbox_bitmap_len = reader.read_u32().map_err(|_| TransformError::InvalidData)?;
-    let bbox_len = reader.read_u32().map_err(|_| TransformError::InvalidData)?;
-    let instruction_len = reader.read_u32().map_err(|_| TransformError::InvalidData)?;
-
-    let header_size = reader.pos();
-    
-    // Calculate stream positions
-    let n_contours_pos = header_size;
-    let n_points_pos = n_contours_pos + n_contours_len as usize;
-    let flag_pos = n_points_pos + n_points_len as usize;
-    let glyph_pos = flag_pos + flag_len as usize;
-    let composite_pos = glyph_pos + glyph_len as usize;
-    let bbox_bitmap_pos = composite_pos + composite_len as usize;
-    let bbox_pos = bbox_bitmap_pos + bbox_bitmap_len as usize;
-    let instruction_pos = bbox_pos + bbox_len as usize;
-
-    // Get stream slices
-    let n_contours_data = &transform_data[n_contours_pos..n_points_pos.min(transform_data.len())];
-    let n_points_data = &transform_data[n_points_pos..flag_pos.min(transform_data.len())];
-    let flag_data = &transform_data[flag_pos..glyph_pos.min(transform_data.len())];
-    let glyph_data = &transform_data[glyph_pos..composite_pos.min(transform_data.len())];
-    let composite_data = &transform_data[composite_pos..bbox_bitmap_pos.min(transform_data.len())];
-    let bbox_bitmap = &transform_data[bbox_bitmap_pos..bbox_pos.min(transform_data.len())];
-    let bbox_data = &transform_data[bbox_pos..instruction_pos.min(transform_data.len())];
-    let instruction_data = &transform_data[instruction_pos..];
-
-    // Output buffers
     let mut glyf_output = Vec::new();
-    let mut loca_offsets = Vec::with_capacity(num_glyphs as usize + 1);
-
-    // Stream readers
-    let mut n_contours_reader = StreamReader::new(n_contours_data);
-    let mut n_points_reader = StreamReader::new(n_points_data);
-    let mut flag_reader = StreamReader::new(flag_data);
-    let mut triplet_decoder = TripletDecoder::new(glyph_data);
-    let mut composite_reader = StreamReader::new(composite_data);
-    let mut bbox_reader = StreamReader::new(bbox_data);
-    let mut instruction_reader = StreamReader::new(instruction_data);
-
-    // Process each glyph
+    let mut loca_offsets = Vec::with_capacity(num_glyphs + 1);
+    let mut x_mins = Vec::with_capacity(num_glyphs);
+
     for glyph_idx in 0..num_glyphs {
         loca_offsets.push(glyf_output.len() as u32);
 
-        let n_contours = n_contours_reader.read_i16()?;
-
-        if n_contours == 0 {
-            // Empty glyph
-            continue;
-        }
-
-        // Check if bbox is explicitly stored
-        let bbox_explicit = if glyph_idx as usize / 8 < bbox_bitmap.len() {
-            (bbox_bitmap[glyph_idx as usize / 8] >> (7 - glyph_idx % 8)) & 1 != 0
+        let n_contours = n_contour_reader.read_i16()?;
+        let bbox_explicit = bit_set(bbox_bitmap, glyph_idx);
+        let explicit_bbox = if bbox_explicit {
+            Some([
+                bbox_reader.read_i16()?,
+                bbox_reader.read_i16()?,
+                bbox_reader.read_i16()?,
+                bbox_reader.read_i16()?,
+            ])
         } else {
-            false
+            None
         };
 
-        if n_contours > 0 {
-            // Simple glyph
-            let glyph_data = reconstruct_simple_glyph(
-                n_contours as u16,
-                &mut n_points_reader,
-                &mut flag_reader,
-                &mut triplet_decoder,
-                &mut instruction_reader,
-                &mut bbox_reader,
-                bbox_explicit,
-            )?;
-            glyf_output.extend_from_slice(&glyph_data);
-        } else {
-            // Composite glyph
-            let glyph_data = reconstruct_composite_glyph(
-                &mut composite_reader,
-                &mut instruction_reader,
-                &mut bbox_reader,
-                bbox_explicit,
-            )?;
-            glyf_output.extend_from_slice(&glyph_data);
-        }
+        let bbox = match n_contours {
+            0 => {
+                // Empty glyph: no outline data, and no box allowed
+                if bbox_explicit {
+                    return Err(TransformError::InvalidGlyph);
+                }
+                x_mins.push(0);
+                continue;
+            }
+            -1 => {
+                // Composite glyphs always store their box explicitly
+                let bbox = explicit_bbox.ok_or(TransformError::InvalidGlyph)?;
+                reconstruct_composite_glyph(
+                    bbox,
+                    &mut composite_reader,
+                    &mut glyph_reader,
+                    &mut instruction_reader,
+                    &mut glyf_output,
+                )?;
+                bbox
+            }
+            n if n > 0 => {
+                let overlap = overlap_bitmap.is_some_and(|bitmap| bit_set(bitmap, glyph_idx));
+                reconstruct_simple_glyph(
+                    n as u16,
+                    explicit_bbox,
+                    overlap,
+                    SimpleGlyphStreams {
+                        n_points: &mut n_points_reader,
+                        flags: &mut flag_reader,
+                        glyph: &mut glyph_reader,
+                        instructions: &mut instruction_reader,
+                    },
+                    &mut glyf_output,
+                )?
+            }
+            _ => return Err(TransformError::InvalidGlyph),
+        };
+        x_mins.push(bbox[0]);
 
         // Align to 4 bytes
         while glyf_output.len() % 4 != 0 {
@@ -371,7 +239,17 @@ pub fn reconstruct_glyf(
 
     loca_offsets.push(glyf_output.len() as u32);
 
-    Ok((glyf_output, loca_offsets))
+    let use_short = header.index_format == 0;
+    if use_short && !can_use_short_loca(&loca_offsets) {
+        return Err(TransformError::InvalidData);
+    }
+
+    Ok(GlyfReconstruction {
+        glyf: glyf_output,
+        loca: generate_loca(&loca_offsets, use_short),
+        x_mins,
+        index_format: header.index_format,
+    })
 }
 
 /// Simple stream reader helper
@@ -395,12 +273,7 @@ impl<'a> StreamReader<'a> {
     }
 
     fn read_i16(&mut self) -> TransformResult<i16> {
-        if self.pos + 2 > self.data.len() {
-            return Err(TransformError::UnexpectedEof);
-        }
-        let v = i16::from_be_bytes([self.data[self.pos], self.data[self.pos + 1]]);
-        self.pos += 2;
-        Ok(v)
+        Ok(self.read_u16()? as i16)
     }
 
     fn read_u16(&mut self) -> TransformResult<u16> {
@@ -414,25 +287,22 @@ impl<'a> StreamReader<'a> {
 
     fn read_255u16(&mut self) -> TransformResult<u16> {
         // WOFF2's variable-length u16 encoding
+        const WORD_CODE: u16 = 253;
+        const ONE_MORE_BYTE_CODE2: u16 = 254;
+        const ONE_MORE_BYTE_CODE1: u16 = 255;
+        const LOWEST_U_CODE: u16 = 253;
+
         let first = self.read_u8()? as u16;
-        if first < 253 {
-            Ok(first)
-        } else if first == 253 {
-            let second = self.read_u8()? as u16;
-            Ok(253 + second)
-        } else if first == 254 {
-            let hi = self.read_u8()? as u16;
-            let lo = self.read_u8()? as u16;
-            Ok(253 + 256 + (hi << 8) + lo)
-        } else {
-            let hi = self.read_u8()? as u16;
-            let lo = self.read_u8()? as u16;
-            Ok((hi << 8) + lo)
+        match first {
+            WORD_CODE => self.read_u16(),
+            ONE_MORE_BYTE_CODE1 => Ok(self.read_u8()? as u16 + LOWEST_U_CODE),
+            ONE_MORE_BYTE_CODE2 => Ok(self.read_u8()? as u16 + LOWEST_U_CODE * 2),
+            _ => Ok(first),
         }
     }
 
     fn read_bytes(&mut self, n: usize) -> TransformResult<&'a [u8]> {
-        if self.pos + n > self.data.len() {
+        if n > self.data.len() - self.pos {
             return Err(TransformError::UnexpectedEof);
         }
         let slice = &self.data[self.pos..self.pos + n];
@@ -441,127 +311,117 @@ impl<'a> StreamReader<'a> {
     }
 }
 
-/// Reconstruct a simple glyph
+/// Substreams consumed by a simple glyph
+struct SimpleGlyphStreams<'s, 'a> {
+    n_points: &'s mut StreamReader<'a>,
+    flags: &'s mut StreamReader<'a>,
+    glyph: &'s mut StreamReader<'a>,
+    instructions: &'s mut StreamReader<'a>,
+}
+
+/// Reconstruct a simple glyph, returning its bounding box
 fn reconstruct_simple_glyph(
     n_contours: u16,
-    n_points_reader: &mut StreamReader,
-    flag_reader: &mut StreamReader,
-    triplet_decoder: &mut TripletDecoder,
-    instruction_reader: &mut StreamReader,
-    bbox_reader: &mut StreamReader,
-    bbox_explicit: bool,
-) -> TransformResult<Vec<u8>> {
-    let mut output = Vec::new();
-
+    explicit_bbox: Option<[i16; 4]>,
+    overlap: bool,
+    streams: SimpleGlyphStreams,
+    output: &mut Vec<u8>,
+) -> TransformResult<[i16; 4]> {
     // Read number of points per contour
-    let mut total_points = 0u16;
+    let mut total_points = 0usize;
     let mut end_pts = Vec::with_capacity(n_contours as usize);
     for _ in 0..n_contours {
-        let n_pts = n_points_reader.read_255u16()?;
+        let n_pts = streams.n_points.read_255u16()? as usize;
         total_points += n_pts;
-        end_pts.push(total_points - 1);
+        if total_points > 0xFFFF {
+            return Err(TransformError::InvalidGlyph);
+        }
+        end_pts.push(total_points.wrapping_sub(1) as u16);
     }
 
-    // Read flags for all points
-    let mut flags = Vec::with_capacity(total_points as usize);
+    // Decode triplets to absolute coordinates
+    let mut points = Vec::with_capacity(total_points);
+    let (mut x, mut y) = (0i32, 0i32);
     for _ in 0..total_points {
-        flags.push(flag_reader.read_u8()?);
-    }
-
-    // Decode triplets to get coordinates
-    let mut x_coords = Vec::with_capacity(total_points as usize);
-    let mut y_coords = Vec::with_capacity(total_points as usize);
-    let mut on_curve = Vec::with_capacity(total_points as usize);
-
-    let mut x = 0i32;
-    let mut y = 0i32;
-
-    for &flag in &flags {
-        let (dx, dy, on) = triplet_decoder.decode_triplet(flag)?;
-        x += dx as i32;
-        y += dy as i32;
-        x_coords.push(x as i16);
-        y_coords.push(y as i16);
-        on_curve.push(on);
+        let flag = streams.flags.read_u8()?;
+        let (dx, dy, on_curve) = decode_triplet(flag, streams.glyph)?;
+        x += dx;
+        y += dy;
+        points.push((x, y, on_curve));
     }
 
     // Calculate or read bounding box
-    let (x_min, y_min, x_max, y_max) = if bbox_explicit {
-        (
-            bbox_reader.read_i16()?,
-            bbox_reader.read_i16()?,
-            bbox_reader.read_i16()?,
-            bbox_reader.read_i16()?,
-        )
-    } else if total_points > 0 {
-        let x_min = *x_coords.iter().min().unwrap_or(&0);
-        let y_min = *y_coords.iter().min().unwrap_or(&0);
-        let x_max = *x_coords.iter().max().unwrap_or(&0);
-        let y_max = *y_coords.iter().max().unwrap_or(&0);
-        (x_min, y_min, x_max, y_max)
-    } else {
-        (0, 0, 0, 0)
+    let bbox = match explicit_bbox {
+        Some(bbox) => bbox,
+        None if points.is_empty() => [0; 4],
+        None => {
+            let mut bbox = [i16::MAX, i16::MAX, i16::MIN, i16::MIN];
+            for &(x, y, _) in &points {
+                bbox[0] = bbox[0].min(x as i16);
+                bbox[1] = bbox[1].min(y as i16);
+                bbox[2] = bbox[2].max(x as i16);
+                bbox[3] = bbox[3].max(y as i16);
+            }
+            bbox
+        }
     };
 
     // Write glyph header
     output.extend_from_slice(&(n_contours as i16).to_be_bytes());
-    output.extend_from_slice(&x_min.to_be_bytes());
-    output.extend_from_slice(&y_min.to_be_bytes());
-    output.extend_from_slice(&x_max.to_be_bytes());
-    output.extend_from_slice(&y_max.to_be_bytes());
+    for value in bbox {
+        output.extend_from_slice(&value.to_be_bytes());
+    }
 
     // Write endPtsOfContours
     for end_pt in &end_pts {
         output.extend_from_slice(&end_pt.to_be_bytes());
     }
 
-    // Read and write instructions
-    let instruction_len = instruction_reader.read_255u16()?;
+    // Instruction length lives in the glyph stream, the bytes in their own
+    let instruction_len = streams.glyph.read_255u16()?;
     output.extend_from_slice(&instruction_len.to_be_bytes());
-    if instruction_len > 0 {
-        let instructions = instruction_reader.read_bytes(instruction_len as usize)?;
-        output.extend_from_slice(instructions);
-    }
-
-    // Write flags (convert from triplet flags to OpenType flags)
-    let mut prev_x = 0i16;
-    let mut prev_y = 0i16;
-    let mut ot_flags = Vec::with_capacity(total_points as usize);
-
-    for i in 0..total_points as usize {
-        let mut flag: u8 = 0;
-        
-        // On-curve flag
-        if on_curve[i] {
-            flag |= 0x01;
+    output.extend_from_slice(streams.instructions.read_bytes(instruction_len as usize)?);
+
+    // Convert to OpenType flags and coordinate deltas
+    let mut ot_flags = Vec::with_capacity(total_points);
+    let mut x_bytes = Vec::new();
+    let mut y_bytes = Vec::new();
+    let (mut prev_x, mut prev_y) = (0i32, 0i32);
+    for &(x, y, on_curve) in &points {
+        let mut flag: u8 = if on_curve { 0x01 } else { 0x00 };
+        if overlap && ot_flags.is_empty() {
+            flag |= 0x40; // OVERLAP_SIMPLE
         }
 
-        let dx = x_coords[i] - prev_x;
-        let dy = y_coords[i] - prev_y;
-
-        // X coordinate encoding
+        let dx = x - prev_x;
         if dx == 0 {
             flag |= 0x10; // x-Same
-        } else if dx >= -255 && dx <= 255 {
+        } else if (-255..=255).contains(&dx) {
             flag |= 0x02; // x-Short
             if dx > 0 {
                 flag |= 0x10; // positive
             }
+            x_bytes.push(dx.unsigned_abs() as u8);
+        } else {
+            x_bytes.extend_from_slice(&(dx as i16).to_be_bytes());
         }
 
-        // Y coordinate encoding
+        let dy = y - prev_y;
         if dy == 0 {
             flag |= 0x20; // y-Same
-        } else if dy >= -255 && dy <= 255 {
+        } else if (-255..=255).contains(&dy) {
             flag |= 0x04; // y-Short
             if dy > 0 {
                 flag |= 0x20; // positive
             }
+            y_bytes.push(dy.unsigned_abs() as u8);
+        } else {
+            y_bytes.extend_from_slice(&(dy as i16).to_be_bytes());
         }
 
         ot_flags.push(flag);
-        prev_x = x_coords[i];
-        prev_y = y_coords[i];
+        prev_x = x;
+        prev_y = y;
     }
 
     // Write flags with RLE
@@ -569,9 +429,9 @@ fn reconstruct_simple_glyph(
     while i < ot_flags.len() {
         let flag = ot_flags[i];
         let mut repeat = 0u8;
-        while i + 1 + (repeat as usize) < ot_flags.len() 
-            && repeat < 255 
-            && ot_flags[i + 1 + (repeat as usize)] == flag 
+        while i + 1 + (repeat as usize) < ot_flags.len()
+            && repeat < 255
+            && ot_flags[i + 1 + (repeat as usize)] == flag
         {
             repeat += 1;
         }
@@ -586,87 +446,38 @@ fn reconstruct_simple_glyph(
         }
     }
 
-    // Write x coordinates
-    prev_x = 0;
-    for i in 0..total_points as usize {
-        let dx = x_coords[i] - prev_x;
-        if dx != 0 {
-            if dx >= -255 && dx <= 255 {
-                output.push(dx.abs() as u8);
-            } else {
-                output.extend_from_slice(&dx.to_be_bytes());
-            }
-        }
-        prev_x = x_coords[i];
-    }
-
-    // Write y coordinates
-    prev_y = 0;
-    for i in 0..total_points as usize {
-        let dy = y_coords[i] - prev_y;
-        if dy != 0 {
-            if dy >= -255 && dy <= 255 {
-                output.push(dy.abs() as u8);
-            } else {
-                output.extend_from_slice(&dy.to_be_bytes());
-            }
-        }
-        prev_y = y_coords[i];
-    }
+    output.extend_from_slice(&x_bytes);
+    output.extend_from_slice(&y_bytes);
 
-    Ok(output)
+    Ok(bbox)
 }
 
 /// Reconstruct a composite glyph
 fn reconstruct_composite_glyph(
+    bbox: [i16; 4],
     composite_reader: &mut StreamReader,
+    glyph_reader: &mut StreamReader,
     instruction_reader: &mut StreamReader,
-    bbox_reader: &mut StreamReader,
-    bbox_explicit: bool,
-) -> TransformResult<Vec<u8>> {
-    let mut output = Vec::new();
-
-    // Read bounding box if explicit
-    let (x_min, y_min, x_max, y_max) = if bbox_explicit {
-        (
-            bbox_reader.read_i16()?,
-            bbox_reader.read_i16()?,
-            bbox_reader.read_i16()?,
-            bbox_reader.read_i16()?,
-        )
-    } else {
-        (0, 0, 0, 0) // Will be calculated by renderer
-    };
+    output: &mut Vec<u8>,
+) -> TransformResult<()> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+    const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
 
     // Write glyph header (n_contours = -1 for composite)
     output.extend_from_slice(&(-1i16).to_be_bytes());
-    output.extend_from_slice(&x_min.to_be_bytes());
-    output.extend_from_slice(&y_min.to_be_bytes());
-    output.extend_from_slice(&x_max.to_be_bytes());
-    output.extend_from_slice(&y_max.to_be_bytes());
+    for value in bbox {
+        output.extend_from_slice(&value.to_be_bytes());
+    }
 
-    // Copy composite data directly (already in OpenType format)
+    // Components are stored in OpenType format; copy them verbatim
     let mut has_instructions = false;
     loop {
         let flags = composite_reader.read_u16()?;
-        let glyph_index = composite_reader.read_u16()?;
-
-        output.extend_from_slice(&flags.to_be_bytes());
-        output.extend_from_slice(&glyph_index.to_be_bytes());
-
-        // Read arguments based on flags
-        const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
-        const WE_HAVE_A_SCALE: u16 = 0x0008;
-        const MORE_COMPONENTS: u16 = 0x0020;
-        const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
-        const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
-        const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
-
         let arg_size = if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
-        let args = composite_reader.read_bytes(arg_size)?;
-        output.extend_from_slice(args);
-
-        // Read transform data
         let transform_size = if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
             8
         } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
@@ -676,31 +487,24 @@ fn reconstruct_composite_glyph(
         } else {
             0
         };
-        if transform_size > 0 {
-            let transform = composite_reader.read_bytes(transform_size)?;
-            output.extend_from_slice(transform);
-        }
 
-        if flags & WE_HAVE_INSTRUCTIONS != 0 {
-            has_instructions = true;
-        }
+        output.extend_from_slice(&flags.to_be_bytes());
+        // Glyph index, arguments and transform
+        output.extend_from_slice(composite_reader.read_bytes(2 + arg_size + transform_size)?);
 
+        has_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
         if flags & MORE_COMPONENTS == 0 {
             break;
         }
     }
 
-    // Write instructions if present
     if has_instructions {
-        let instruction_len = instruction_reader.read_255u16()?;
+        let instruction_len = glyph_reader.read_255u16()?;
         output.extend_from_slice(&instruction_len.to_be_bytes());
-        if instruction_len > 0 {
-            let instructions = instruction_reader.read_bytes(instruction_len as usize)?;
-            output.extend_from_slice(instructions);
-        }
+        output.extend_from_slice(instruction_reader.read_bytes(instruction_len as usize)?);
     }
 
-    Ok(output)
+    Ok(())
 }
 
 // ============================================================================
@@ -735,50 +539,49 @@ pub fn can_use_short_loca(offsets: &[u32]) -> bool {
 // Hmtx Table Transform
 // ============================================================================
 
-/// Reconstruct hmtx table from WOFF2 transformed data
+/// Reconstruct hmtx table from WOFF2 transformed data.
+///
+/// Side bearings omitted by the encoder are restored from the glyph
+/// bounding boxes (`x_mins`) of the reconstructed glyf table.
 pub fn reconstruct_hmtx(
     transform_data: &[u8],
     num_glyphs: u16,
     num_h_metrics: u16,
+    x_mins: &[i16],
 ) -> TransformResult<Vec<u8>> {
-    if transform_data.is_empty() {
+    if num_h_metrics == 0 || num_h_metrics > num_glyphs || x_mins.len() != num_glyphs as usize {
         return Err(TransformError::InvalidData);
     }
 
     let mut reader = StreamReader::new(transform_data);
-    let mut output = Vec::new();
 
-    // Read flags
+    // Bit 0: proportional lsbs omitted, bit 1: monospaced lsbs omitted
     let flags = reader.read_u8()?;
-    let has_proportional_lsb = (flags & 0x01) != 0;
-    let has_monospace_lsb = (flags & 0x02) != 0;
+    if flags & 0xFC != 0 || flags & 0x03 == 0 {
+        return Err(TransformError::UnsupportedTransform);
+    }
 
-    // Read advance widths (delta encoded)
-    let mut advance = 0u16;
+    let mut advances = Vec::with_capacity(num_h_metrics as usize);
     for _ in 0..num_h_metrics {
-        let delta = reader.read_i16()? as i32;
-        advance = (advance as i32 + delta) as u16;
-        output.extend_from_slice(&advance.to_be_bytes());
-
-        // Placeholder for LSB (will be filled below)
-        if has_proportional_lsb {
-            let lsb = reader.read_i16()?;
-            output.extend_from_slice(&lsb.to_be_bytes());
+        advances.push(reader.read_u16()?);
+    }
+
+    let mut lsbs = Vec::with_capacity(num_glyphs as usize);
+    for (glyph, &x_min) in x_mins.iter().enumerate() {
+        let omitted = if glyph < num_h_metrics as usize {
+            flags & 0x01 != 0
         } else {
-            output.extend_from_slice(&0i16.to_be_bytes());
-        }
+            flags & 0x02 != 0
+        };
+        lsbs.push(if omitted { x_min } else { reader.read_i16()? });
     }
 
-    // Additional LSBs for monospace section
-    if has_monospace_lsb {
-        for _ in num_h_metrics..num_glyphs {
-            let lsb = reader.read_i16()?;
-            output.extend_from_slice(&lsb.to_be_bytes());
-        }
-    } else {
-        for _ in num_h_metrics..num_glyphs {
-            output.extend_from_slice(&0i16.to_be_bytes());
+    let mut output = Vec::with_capacity(num_h_metrics as usize * 2 + num_glyphs as usize * 2);
+    for (glyph, lsb) in lsbs.iter().enumerate() {
+        if let Some(advance) = advances.get(glyph) {
+            output.extend_from_slice(&advance.to_be_bytes());
         }
+        output.extend_from_slice(&lsb.to_be_bytes());
     }
 
     Ok(output)
@@ -809,27 +612,72 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_triplet_tables() {
-        // Verify lookup table consistency
-        assert_eq!(TRIPLET_BYTE_COUNT[0], 1);
-        assert_eq!(TRIPLET_BYTE_COUNT[10], 2);
-        assert_eq!(TRIPLET_BYTE_COUNT[58], 3);
-        assert_eq!(TRIPLET_BYTE_COUNT[84], 4);
-        assert_eq!(TRIPLET_BYTE_COUNT[120], 5);
+    fn test_decode_triplet() {
+        // dy only, positive
+        let mut reader = StreamReader::new(&[7]);
+        assert_eq!(decode_triplet(1, &mut reader).unwrap(), (0, 7, true));
+
+        // dx only, positive; the high bit marks an off-curve point
+        let mut reader = StreamReader::new(&[100]);
+        assert_eq!(decode_triplet(0x80 | 11, &mut reader).unwrap(), (100, 0, false));
+
+        // Two bytes: negative dx, positive dy
+        let mut reader = StreamReader::new(&[49, 99]);
+        assert_eq!(decode_triplet(86, &mut reader).unwrap(), (-50, 100, true));
+
+        // Full 16-bit deltas
+        let mut reader = StreamReader::new(&[0x01, 0x00, 0x02, 0x00]);
+        assert_eq!(decode_triplet(127, &mut reader).unwrap(), (256, 512, true));
     }
 
     #[test]
     fn test_stream_reader_255u16() {
         // Test 255UInt16 encoding
-        let data = [100, 253, 50, 254, 0x01, 0x00, 255, 0x10, 0x00];
+        let data = [100, 255, 50, 254, 0x10, 253, 0x10, 0x00];
         let mut reader = StreamReader::new(&data);
 
         assert_eq!(reader.read_255u16().unwrap(), 100);
         assert_eq!(reader.read_255u16().unwrap(), 253 + 50);
-        assert_eq!(reader.read_255u16().unwrap(), 253 + 256 + 256);
+        assert_eq!(reader.read_255u16().unwrap(), 506 + 0x10);
         assert_eq!(reader.read_255u16().unwrap(), 0x1000);
     }
 
+    #[test]
+    fn test_reconstruct_simple_glyph() {
+        let mut data = vec![0, 0, 0, 0, 0, 1, 0, 0];
+        for size in [2u32, 1, 3, 5, 0, 4, 0] {
+            data.extend_from_slice(&size.to_be_bytes());
+        }
+        data.extend_from_slice(&[0, 1]); // nContours
+        data.push(3); // nPoints
+        data.extend_from_slice(&[1, 11, 86]); // flags
+        data.extend_from_slice(&[0, 100, 49, 99, 0]); // triplets, instruction length
+        data.extend_from_slice(&[0, 0, 0, 0]); // bbox bitmap
+
+        let result = reconstruct_glyf(&data).unwrap();
+        assert_eq!(
+            result.glyf,
+            [
+                0, 1, 0, 0, 0, 0, 0, 100, 0, 100, // contours, bbox
+                0, 2, 0, 0, // endPts, instructionLength
+                0x31, 0x33, 0x27, // flags
+                100, 50, 100, // x and y deltas
+            ]
+        );
+        assert_eq!(result.loca, [0, 0, 0, 10]);
+        assert_eq!(result.x_mins, [0]);
+    }
+
+    #[test]
+    fn test_reconstruct_hmtx() {
+        let hmtx = reconstruct_hmtx(&[0x03, 0x02, 0x00], 2, 1, &[5, 7]).unwrap();
+        assert_eq!(hmtx, [0x02, 0x00, 0, 5, 0, 7]);
+
+        // Explicit monospaced lsbs
+        let hmtx = reconstruct_hmtx(&[0x01, 0x02, 0x00, 0xFF, 0xFE], 2, 1, &[5, 7]).unwrap();
+        assert_eq!(hmtx, [0x02, 0x00, 0, 5, 0xFF, 0xFE]);
+    }
+
     #[test]
     fn test_generate_loca_short() {
         let offsets = vec![0, 100, 200, 400];
@@ -0,0 +1,250 @@
+//! Hyphenation
+//!
+//! Liang's pattern-based hyphenation, as used by TeX. Patterns such as
+//! `hy3ph` give inter-letter values; after matching every pattern against a
+//! word, odd values mark hyphenation points. Dictionaries for English,
+//! French, Spanish, Italian and Portuguese are embedded and others can be
+//! registered at runtime. The embedded patterns come from TeX's hyph-utf8
+//! and keep their upstream licenses, listed in NOTICE.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// CSS `hyphens` property
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Hyphens {
+    /// Never hyphenate, not even at soft hyphens
+    None,
+    /// Hyphenate only at soft hyphens (U+00AD)
+    #[default]
+    Manual,
+    /// Hyphenate with the dictionary of the content language
+    Auto,
+}
+
+impl Hyphens {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "manual" => Some(Self::Manual),
+            "auto" => Some(Self::Auto),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Manual => "manual",
+            Self::Auto => "auto",
+        }
+    }
+}
+
+/// Pattern-based hyphenator for one language
+#[derive(Debug, Clone)]
+pub struct Hyphenator {
+    /// Pattern letters to the values between and around them
+    patterns: HashMap<String, Vec<u8>>,
+    /// Whole words with fixed hyphenation points (char offsets)
+    exceptions: HashMap<String, Vec<usize>>,
+    /// Longest pattern in chars
+    max_pattern_len: usize,
+    /// Minimum chars before the first hyphen
+    left_min: usize,
+    /// Minimum chars after the last hyphen
+    right_min: usize,
+}
+
+impl Hyphenator {
+    /// Build from TeX patterns separated by whitespace; `%` starts a comment
+    pub fn new(patterns: &str, left_min: usize, right_min: usize) -> Self {
+        let mut map = HashMap::new();
+        let mut max_pattern_len = 0;
+        for line in patterns.lines() {
+            let line = line.split('%').next().unwrap_or("");
+            for pattern in line.split_whitespace() {
+                let mut letters = String::new();
+                let mut values = vec![0];
+                for c in pattern.chars() {
+                    match c.to_digit(10) {
+                        Some(d) => *values.last_mut().unwrap() = d as u8,
+                        None => {
+                            letters.push(c);
+                            values.push(0);
+                        }
+                    }
+                }
+                max_pattern_len = max_pattern_len.max(values.len() - 1);
+                map.insert(letters, values);
+            }
+        }
+        Self {
+            patterns: map,
+            exceptions: HashMap::new(),
+            max_pattern_len,
+            left_min: left_min.max(1),
+            right_min: right_min.max(1),
+        }
+    }
+
+    /// Add exception words written with hyphens, e.g. `ta-ble`
+    pub fn with_exceptions(mut self, exceptions: &str) -> Self {
+        for word in exceptions.split_whitespace() {
+            let mut points = Vec::new();
+            let mut letters = String::new();
+            for c in word.chars() {
+                if c == '-' {
+                    points.push(letters.chars().count());
+                } else {
+                    letters.extend(c.to_lowercase());
+                }
+            }
+            self.exceptions.insert(letters, points);
+        }
+        self
+    }
+
+    /// Number of patterns
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Byte offsets in `word` where it may be hyphenated
+    pub fn hyphenate(&self, word: &str) -> Vec<usize> {
+        let chars: Vec<(usize, char)> = word.char_indices().collect();
+        let n = chars.len();
+        if n < self.left_min + self.right_min {
+            return Vec::new();
+        }
+
+        let lower: Vec<char> = chars.iter().map(|&(_, c)| normalize(c)).collect();
+        let points: Vec<usize> = match self.exceptions.get(&lower.iter().collect::<String>()) {
+            Some(points) => points.clone(),
+            None => {
+                // Values between the chars of ".word."
+                let dotted: Vec<char> = std::iter::once('.').chain(lower.iter().copied()).chain(std::iter::once('.')).collect();
+                let mut values = vec![0u8; dotted.len() + 1];
+                let mut key = String::new();
+                for start in 0..dotted.len() {
+                    key.clear();
+                    for &c in dotted[start..].iter().take(self.max_pattern_len) {
+                        key.push(c);
+                        if let Some(pattern) = self.patterns.get(&key) {
+                            for (i, &v) in pattern.iter().enumerate() {
+                                values[start + i] = values[start + i].max(v);
+                            }
+                        }
+                    }
+                }
+                // Point i (before char i of the word) is value i + 1 of the dotted word
+                (1..n).filter(|&i| values[i + 1] % 2 == 1).collect()
+            }
+        };
+
+        points
+            .into_iter()
+            .filter(|&i| i >= self.left_min && i <= n - self.right_min)
+            .map(|i| chars[i].0)
+            .collect()
+    }
+}
+
+/// Lowercase a char without changing its length, as patterns expect
+fn normalize(c: char) -> char {
+    if c == '\u{2019}' {
+        return '\'';
+    }
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+type Registry = RwLock<HashMap<String, Arc<Hyphenator>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Hyphenator for a BCP 47 language tag such as `en-US`; `None` if there is
+/// no dictionary for the language
+pub fn hyphenator(lang: &str) -> Option<Arc<Hyphenator>> {
+    let lang = lang.trim().to_ascii_lowercase();
+    let primary = lang.split(['-', '_']).next().unwrap_or("");
+    {
+        let registry = registry().read().unwrap();
+        if let Some(hyphenator) = registry.get(&lang).or_else(|| registry.get(primary)) {
+            return Some(hyphenator.clone());
+        }
+    }
+
+    let hyphenator = Arc::new(builtin(primary)?);
+    registry().write().unwrap().entry(primary.to_string()).or_insert(hyphenator.clone());
+    Some(hyphenator)
+}
+
+/// Register a dictionary for a language tag, replacing any previous one
+pub fn register(lang: &str, hyphenator: Hyphenator) {
+    registry().write().unwrap().insert(lang.trim().to_ascii_lowercase(), Arc::new(hyphenator));
+}
+
+/// Embedded dictionary for a primary language subtag
+fn builtin(primary: &str) -> Option<Hyphenator> {
+    Some(match primary {
+        "en" => Hyphenator::new(include_str!("patterns/en-us.pat"), 2, 3).with_exceptions(
+            "as-so-ciate as-so-ciates dec-li-na-tion oblig-a-tory phil-an-thropic present presents \
+             project projects reci-procity re-cog-ni-zance ref-or-ma-tion ret-ri-bu-tion ta-ble",
+        ),
+        "fr" => Hyphenator::new(include_str!("patterns/fr.pat"), 2, 2),
+        "es" => Hyphenator::new(include_str!("patterns/es.pat"), 2, 2),
+        "it" => Hyphenator::new(include_str!("patterns/it.pat"), 2, 2),
+        "pt" => Hyphenator::new(include_str!("patterns/pt.pat"), 2, 3),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn syllables(lang: &str, word: &str) -> String {
+        let hyphenator = hyphenator(lang).unwrap();
+        let mut out = String::new();
+        let mut last = 0;
+        for point in hyphenator.hyphenate(word) {
+            out.push_str(&word[last..point]);
+            out.push('-');
+            last = point;
+        }
+        out.push_str(&word[last..]);
+        out
+    }
+
+    #[test]
+    fn test_english() {
+        assert_eq!(syllables("en-US", "hyphenation"), "hy-phen-ation");
+        assert_eq!(syllables("en", "extensive"), "ex-ten-sive");
+        assert_eq!(syllables("en-GB", "Probability"), "Prob-a-bil-ity");
+        assert_eq!(syllables("en", "table"), "ta-ble");
+        assert_eq!(syllables("en", "hi"), "hi");
+        assert!(hyphenator("xx").is_none());
+    }
+
+    #[test]
+    fn test_other_languages() {
+        assert_eq!(syllables("es", "ventana"), "ven-ta-na");
+        assert_eq!(syllables("it", "cappello"), "cap-pel-lo");
+        assert_eq!(syllables("fr", "développement"), "dé-ve-lop-pe-ment");
+
+        register("la", Hyphenator::new("1ba 1la", 1, 1));
+        assert_eq!(syllables("la", "balaba"), "ba-la-ba");
+        assert_eq!(Hyphens::from_str("AUTO"), Some(Hyphens::Auto));
+    }
+}
@@ -0,0 +1,123 @@
+//! Justification
+//!
+//! `text-align: justify` stretches every line but the last to the line box
+//! by spreading the free space evenly over justification opportunities.
+//! Which positions are opportunities depends on `text-justify`: word
+//! separators, the gaps between typographic letter units (grapheme
+//! clusters), or, by default, word separators plus the gaps around CJK
+//! characters, which are set without spaces. Cursive scripts are never
+//! stretched between letters.
+
+use crate::segmentation::{grapheme_boundaries, is_ideographic};
+
+/// CSS `text-justify`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextJustify {
+    /// Word separators, and inter-character spacing in CJK text
+    #[default]
+    Auto,
+    /// Justification disabled
+    None,
+    /// Word separators only
+    InterWord,
+    /// Between typographic letter units
+    InterCharacter,
+}
+
+impl TextJustify {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "none" => Some(Self::None),
+            "inter-word" => Some(Self::InterWord),
+            // Legacy alias
+            "inter-character" | "distribute" => Some(Self::InterCharacter),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::None => "none",
+            Self::InterWord => "inter-word",
+            Self::InterCharacter => "inter-character",
+        }
+    }
+}
+
+/// Byte offsets in `text` at which justification space is inserted. Each
+/// offset is a boundary inside the text; the space goes before the content
+/// that follows it.
+pub fn justification_opportunities(text: &str, mode: TextJustify) -> Vec<usize> {
+    if mode == TextJustify::None {
+        return Vec::new();
+    }
+    let mut opportunities = Vec::new();
+    let boundaries = grapheme_boundaries(text);
+    for pair in boundaries.windows(3) {
+        let (before, after) = (&text[pair[0]..pair[1]], &text[pair[1]..pair[2]]);
+        let (Some(last), Some(next)) = (before.chars().next_back(), after.chars().next()) else { continue };
+        let opportunity = match mode {
+            TextJustify::InterWord => is_word_separator(last),
+            TextJustify::InterCharacter => !(is_cursive(last) && is_cursive(next)),
+            _ => is_word_separator(last) || is_cjk(last) || is_cjk(next),
+        };
+        if opportunity {
+            opportunities.push(pair[1]);
+        }
+    }
+    opportunities
+}
+
+/// Lines ending in a forced break are set like the last line
+pub(crate) fn ends_with_forced_break(line: &str) -> bool {
+    line.ends_with(['\n', '\r', '\u{0B}', '\u{0C}', '\u{85}', '\u{2028}', '\u{2029}'])
+}
+
+/// Word-separator characters (CSS Text 3 §7.1)
+fn is_word_separator(c: char) -> bool {
+    matches!(c, ' ' | '\u{A0}' | '\u{1361}' | '\u{10100}' | '\u{10101}' | '\u{1039F}' | '\u{1091F}')
+}
+
+/// Han, kana, bopomofo and fullwidth forms
+fn is_cjk(c: char) -> bool {
+    is_ideographic(c) || matches!(c as u32, 0x3000..=0x303F | 0x3040..=0x30FF | 0x3100..=0x312F | 0x31F0..=0x31FF | 0xFF01..=0xFF60)
+}
+
+/// Arabic, Syriac, N'Ko and Mongolian, whose letters join
+fn is_cursive(c: char) -> bool {
+    matches!(c as u32, 0x0600..=0x06FF | 0x0700..=0x074F | 0x0750..=0x077F | 0x07C0..=0x07FF | 0x0860..=0x08FF |
+        0x1800..=0x18AF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::TextLine;
+
+    #[test]
+    fn test_opportunities() {
+        assert_eq!(justification_opportunities("a bc d", TextJustify::InterWord), vec![2, 5]);
+        assert_eq!(justification_opportunities("a bc d", TextJustify::None), Vec::<usize>::new());
+        assert_eq!(justification_opportunities("a bc d", TextJustify::InterCharacter), vec![1, 2, 3, 4, 5]);
+        // Combining marks stay with their base
+        assert_eq!(justification_opportunities("e\u{301}x", TextJustify::InterCharacter), vec![3]);
+        // Joined Arabic letters are not pulled apart
+        assert_eq!(justification_opportunities("سلام", TextJustify::InterCharacter), Vec::<usize>::new());
+
+        let line = TextLine {
+            start: 0, end: 6, width: 100.0, x_offset: 0.0, hyphenated: false,
+            justification_gap: 4.0, justification_opportunities: vec![2, 5],
+        };
+        assert_eq!((line.justification_offset(1), line.justification_offset(2), line.justification_offset(5)), (0.0, 4.0, 8.0));
+    }
+
+    #[test]
+    fn test_auto_cjk() {
+        // Between ideographs and kana, and at the space before Latin text
+        assert_eq!(justification_opportunities("漢字かな ab", TextJustify::Auto), vec![3, 6, 9, 12, 13]);
+        assert_eq!(TextJustify::from_str("distribute"), Some(TextJustify::InterCharacter));
+        assert!(ends_with_forced_break("line\n") && !ends_with_forced_break("line "));
+    }
+}
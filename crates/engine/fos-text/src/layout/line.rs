@@ -1,4 +1,22 @@
-//! Line breaking (simplified UAX #14)
+//! Line breaking (UAX #14)
+//!
+//! Break opportunities from the pair rules of the Unicode line breaking
+//! algorithm: no breaks before closing punctuation or after opening
+//! punctuation, inside numbers such as `$3,000.50`, or between a base and
+//! its combining marks; breaks between ideographs and after spaces and
+//! hyphens. Thai, Lao, Khmer and Myanmar words are not found with a
+//! dictionary, so their text only breaks at spaces.
+
+use super::hyphenation::{hyphenator, Hyphens};
+use crate::font::emoji::is_emoji;
+use crate::segmentation::{hangul_type, is_decimal_digit, is_hebrew_letter, is_ideographic, Hangul};
+
+/// Soft hyphen (U+00AD), a manual hyphenation opportunity
+const SOFT_HYPHEN: char = '\u{AD}';
+
+/// Text inserted at the end of a hyphenated line; hyphen-minus, which
+/// every font has
+pub const HYPHEN: &str = "-";
 
 /// Line break opportunity
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -9,99 +27,383 @@ pub enum BreakOpportunity {
     AllowBreak,
     /// Must break (hard break - newline)
     MustBreak,
+    /// Break allowed by inserting a hyphen
+    Hyphen,
+}
+
+/// A line produced by the line breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BrokenLine {
+    /// Start byte offset
+    pub start: usize,
+    /// End byte offset (exclusive)
+    pub end: usize,
+    /// The line ends at a hyphenation point and needs a hyphen
+    pub hyphenated: bool,
+}
+
+/// Line breaking class (UAX #14), after the default resolution of
+/// ambiguous (AI, SG, XX → AL), South East Asian (SA → AL or CM) and
+/// conditional Japanese starter (CJ → ID, as CSS `line-break: normal`)
+/// characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineBreakClass {
+    /// Mandatory break (form feed, line and paragraph separators)
+    Bk,
+    Cr,
+    Lf,
+    /// Next line (U+0085)
+    Nl,
+    Sp,
+    /// Zero width space
+    Zw,
+    Zwj,
+    /// Word joiner
+    Wj,
+    /// Non-breaking glue (NBSP)
+    Gl,
+    /// Combining mark
+    Cm,
+    /// Break after
+    Ba,
+    /// Break before
+    Bb,
+    /// Break on either side of an em dash
+    B2,
+    Hy,
+    /// Contingent break opportunity (objects)
+    Cb,
+    /// Close punctuation
+    Cl,
+    /// Close parenthesis
+    Cp,
+    /// Open punctuation
+    Op,
+    /// Exclamation and interrogation
+    Ex,
+    /// Infix numeric separator
+    Is,
+    /// Symbol allowing a break after (slash)
+    Sy,
+    /// Quotation
+    Qu,
+    /// Nonstarter
+    Ns,
+    /// Inseparable (ellipsis)
+    In,
+    /// Prefix numeric (currency)
+    Pr,
+    /// Postfix numeric (percent)
+    Po,
+    Nu,
+    /// Alphabetic
+    Al,
+    /// Hebrew letter
+    Hl,
+    /// Ideographic
+    Id,
+    /// Emoji base
+    Eb,
+    /// Emoji modifier
+    Em,
+    /// Regional indicator
+    Ri,
+    /// Hangul leading jamo, vowel jamo, trailing jamo and syllables
+    Jl,
+    Jv,
+    Jt,
+    H2,
+    H3,
+}
+
+impl LineBreakClass {
+    pub fn of(c: char) -> Self {
+        use LineBreakClass::*;
+        match c {
+            '\u{000B}' | '\u{000C}' | '\u{2028}' | '\u{2029}' => Bk,
+            '\r' => Cr,
+            '\n' => Lf,
+            '\u{0085}' => Nl,
+            ' ' => Sp,
+            '\u{200B}' => Zw,
+            '\u{200D}' => Zwj,
+            '\u{2060}' | '\u{FEFF}' => Wj,
+            '\u{00A0}' | '\u{034F}' | '\u{180E}' | '\u{2007}' | '\u{2011}' | '\u{202F}' | '\u{0F08}' | '\u{0F0C}' | '\u{0F12}' => Gl,
+            '\t' | SOFT_HYPHEN | '|' | '\u{1680}' | '\u{2000}'..='\u{2006}' | '\u{2008}'..='\u{200A}' | '\u{2010}'
+            | '\u{2012}' | '\u{2013}' | '\u{2027}' | '\u{205F}' | '\u{3000}' | '\u{0F0B}' => Ba,
+            '\u{00B4}' | '\u{02C8}' | '\u{02CC}' | '\u{02DF}' | '\u{1FFD}' => Bb,
+            '\u{2014}' => B2,
+            '-' => Hy,
+            '\u{FFFC}' => Cb,
+            '}' | '\u{0F3B}' | '\u{0F3D}' | '\u{2046}' | '\u{207E}' | '\u{208E}' | '\u{2309}' | '\u{230B}' | '\u{232A}'
+            | '\u{3001}' | '\u{3002}' | '\u{3009}' | '\u{300B}' | '\u{300D}' | '\u{300F}' | '\u{3011}' | '\u{3015}'
+            | '\u{3017}' | '\u{3019}' | '\u{301B}' | '\u{301E}' | '\u{301F}' | '\u{FE11}' | '\u{FE12}' | '\u{FE50}'
+            | '\u{FE52}' | '\u{FF0C}' | '\u{FF0E}' | '\u{FF5D}' | '\u{FF60}' | '\u{FF61}' | '\u{FF63}' | '\u{FF64}' => Cl,
+            ')' | ']' | '\u{FF09}' | '\u{FF3D}' => Cp,
+            '(' | '[' | '{' | '\u{00A1}' | '\u{00BF}' | '\u{0F3A}' | '\u{0F3C}' | '\u{201A}' | '\u{201E}' | '\u{2045}'
+            | '\u{207D}' | '\u{208D}' | '\u{2308}' | '\u{230A}' | '\u{2329}' | '\u{3008}' | '\u{300A}' | '\u{300C}'
+            | '\u{300E}' | '\u{3010}' | '\u{3014}' | '\u{3016}' | '\u{3018}' | '\u{301A}' | '\u{301D}' | '\u{FF08}'
+            | '\u{FF3B}' | '\u{FF5B}' | '\u{FF5F}' | '\u{FF62}' => Op,
+            '!' | '?' | '\u{05C6}' | '\u{061B}' | '\u{061E}' | '\u{061F}' | '\u{06D4}' | '\u{07F9}' | '\u{0F0D}'..='\u{0F11}'
+            | '\u{FE15}' | '\u{FE16}' | '\u{FE56}' | '\u{FE57}' | '\u{FF01}' | '\u{FF1F}' => Ex,
+            ',' | '.' | ':' | ';' | '\u{037E}' | '\u{0589}' | '\u{060C}' | '\u{060D}' | '\u{07F8}' | '\u{2044}'
+            | '\u{FE10}' | '\u{FE13}' | '\u{FE14}' => Is,
+            '/' => Sy,
+            '"' | '\'' | '\u{00AB}' | '\u{00BB}' | '\u{2018}' | '\u{2019}' | '\u{201B}'..='\u{201D}' | '\u{201F}'
+            | '\u{2039}' | '\u{203A}' | '\u{275B}'..='\u{2760}' | '\u{2E00}'..='\u{2E0D}' => Qu,
+            '\u{17D6}' | '\u{203C}' | '\u{203D}' | '\u{2047}'..='\u{2049}' | '\u{3005}' | '\u{301C}' | '\u{303B}'
+            | '\u{303C}' | '\u{309B}'..='\u{309E}' | '\u{30A0}' | '\u{30FB}' | '\u{30FD}' | '\u{30FE}' | '\u{A015}'
+            | '\u{FE54}' | '\u{FE55}' | '\u{FF1A}' | '\u{FF1B}' | '\u{FF65}' | '\u{FF9E}' | '\u{FF9F}' => Ns,
+            '\u{2024}'..='\u{2026}' | '\u{22EF}' | '\u{FE19}' => In,
+            '$' | '+' | '\\' | '\u{00A3}'..='\u{00A5}' | '\u{00B1}' | '\u{20A0}'..='\u{20A6}' | '\u{20A8}'..='\u{20B5}'
+            | '\u{20B7}'..='\u{20BA}' | '\u{20BC}' | '\u{20BD}' | '\u{20BF}' | '\u{2116}' | '\u{2212}' | '\u{2213}'
+            | '\u{FE69}' | '\u{FF04}' | '\u{FFE1}' | '\u{FFE5}' | '\u{FFE6}' => Pr,
+            '%' | '\u{00A2}' | '\u{00B0}' | '\u{060A}' | '\u{066A}' | '\u{2030}'..='\u{2037}' | '\u{20A7}' | '\u{20B6}'
+            | '\u{20BB}' | '\u{20BE}' | '\u{2103}' | '\u{2109}' | '\u{FE6A}' | '\u{FF05}' | '\u{FFE0}' => Po,
+            '\u{1F1E6}'..='\u{1F1FF}' => Ri,
+            '\u{1F3FB}'..='\u{1F3FF}' => Em,
+            _ if is_emoji_base(c) => Eb,
+            _ if is_decimal_digit(c) => Nu,
+            _ if is_hebrew_letter(c) => Hl,
+            _ if is_combining(c) => Cm,
+            _ => match hangul_type(c) {
+                Some(Hangul::L) => Jl,
+                Some(Hangul::V) => Jv,
+                Some(Hangul::T) => Jt,
+                Some(Hangul::Lv) => H2,
+                Some(Hangul::Lvt) => H3,
+                None if is_ideographic_break(c) => Id,
+                None => Al,
+            },
+        }
+    }
 }
 
-/// Line breaker (simplified UAX #14 implementation)
+/// Line breaker (UAX #14)
 pub struct LineBreaker;
 
 impl LineBreaker {
-    /// Find break opportunities in text
+    /// Find break opportunities in text: the byte offset after which a
+    /// line may or must end
     pub fn break_opportunities(text: &str) -> Vec<(usize, BreakOpportunity)> {
+        use LineBreakClass::*;
         let mut breaks = Vec::new();
-        let mut chars = text.char_indices().peekable();
-        
-        while let Some((i, c)) = chars.next() {
-            let opp = match c {
-                // Hard breaks
-                '\n' => BreakOpportunity::MustBreak,
-                '\r' => {
-                    // CRLF
-                    if chars.peek().map(|(_, c)| *c) == Some('\n') {
-                        chars.next();
+        let mut chars = text.char_indices().map(|(i, c)| (i, c, LineBreakClass::of(c)));
+        let Some((_, first_char, first)) = chars.next() else {
+            return breaks;
+        };
+
+        // A leading combining mark stands alone (LB10)
+        let mut prev = if matches!(first, Cm | Zwj) { Al } else { first };
+        let mut prev_char = first_char;
+        let mut prev_raw = first;
+        // Class before a run of spaces, for LB14-LB17
+        let mut before_spaces = prev;
+        let mut after_zw = prev == Zw;
+        // HL followed by HY or BA (LB21a)
+        let mut hebrew_hyphen = false;
+        let mut regional_indicators = usize::from(prev == Ri);
+
+        for (i, c, raw) in chars {
+            // LB9: a base followed by marks behaves as the base
+            let attaches = matches!(raw, Cm | Zwj) && !matches!(prev, Bk | Cr | Lf | Nl | Sp | Zw);
+            let cur = if matches!(raw, Cm | Zwj) { Al } else { raw };
+
+            let opportunity = match (prev, cur) {
+                (Cr, Lf) => None,
+                (Bk | Cr | Lf | Nl, _) => Some(BreakOpportunity::MustBreak),
+                (_, Bk | Cr | Lf | Nl | Sp | Zw) => None,
+                _ if after_zw => Some(BreakOpportunity::AllowBreak),
+                _ if prev_raw == Zwj || attaches => None,
+                (Wj | Gl, _) | (_, Wj) => None,
+                (p, Gl) if !matches!(p, Sp | Ba | Hy) => None,
+                (_, Cl | Cp | Ex | Is | Sy) => None,
+                _ if before_spaces == Op => None,
+                (_, Op) if before_spaces == Qu => None,
+                (_, Ns) if matches!(before_spaces, Cl | Cp) => None,
+                (_, B2) if before_spaces == B2 => None,
+                (Sp, _) => Some(BreakOpportunity::AllowBreak),
+                (Qu, _) | (_, Qu) => None,
+                (Cb, _) | (_, Cb) => Some(BreakOpportunity::AllowBreak),
+                (_, Ba | Hy | Ns) | (Bb, _) => None,
+                _ if hebrew_hyphen => None,
+                (Sy, Hl) => None,
+                (_, In) => None,
+                (Al | Hl, Nu) | (Nu, Al | Hl) => None,
+                (Pr, Id | Eb | Em) | (Id | Eb | Em, Po) => None,
+                (Pr | Po, Al | Hl) | (Al | Hl, Pr | Po) => None,
+                // Numbers with their prefixes, suffixes and separators (LB25)
+                (Cl | Cp | Nu, Po | Pr) | (Po | Pr, Op | Nu) | (Hy | Is | Nu | Sy, Nu) => None,
+                (Jl, Jl | Jv | H2 | H3) | (Jv | H2, Jv | Jt) | (Jt | H3, Jt) => None,
+                (Jl | Jv | Jt | H2 | H3, Po) | (Pr, Jl | Jv | Jt | H2 | H3) => None,
+                (Al | Hl, Al | Hl) | (Is, Al | Hl) => None,
+                (Al | Hl | Nu, Op) if !is_wide(c) => None,
+                (Cp, Al | Hl | Nu) if !is_wide(prev_char) => None,
+                (Ri, Ri) if regional_indicators % 2 == 1 => None,
+                (Eb, Em) => None,
+                (Ba, _) if prev_char == SOFT_HYPHEN => Some(BreakOpportunity::Hyphen),
+                _ => Some(BreakOpportunity::AllowBreak),
+            };
+            if let Some(opportunity) = opportunity {
+                breaks.push((i, opportunity));
+            }
+
+            prev_raw = raw;
+            if attaches {
+                continue;
+            }
+            hebrew_hyphen = matches!(cur, Hy | Ba) && prev == Hl;
+            regional_indicators = if cur == Ri { regional_indicators + 1 } else { 0 };
+            after_zw = cur == Zw || (cur == Sp && after_zw);
+            if cur != Sp {
+                before_spaces = cur;
+            }
+            prev = cur;
+            prev_char = c;
+        }
+
+        // A final hard break still ends its line
+        if matches!(prev, Bk | Cr | Lf | Nl) {
+            breaks.push((text.len(), BreakOpportunity::MustBreak));
+        }
+        breaks
+    }
+    
+    /// Find break opportunities, with hyphenation opportunities for the
+    /// `hyphens` property and content language
+    pub fn hyphenation_opportunities(text: &str, hyphens: Hyphens, lang: Option<&str>) -> Vec<(usize, BreakOpportunity)> {
+        let mut breaks = Self::break_opportunities(text);
+        match hyphens {
+            Hyphens::None => breaks.retain(|(_, opp)| *opp != BreakOpportunity::Hyphen),
+            Hyphens::Manual => {}
+            Hyphens::Auto => {
+                let Some(hyphenator) = lang.and_then(hyphenator) else {
+                    return breaks;
+                };
+                for (start, word) in words(text) {
+                    // Soft hyphens in a word replace automatic hyphenation
+                    if word.contains(SOFT_HYPHEN) {
+                        continue;
                     }
-                    BreakOpportunity::MustBreak
+                    breaks.extend(hyphenator.hyphenate(word).into_iter().map(|i| (start + i, BreakOpportunity::Hyphen)));
                 }
-                // Soft breaks after space
-                ' ' | '\t' => BreakOpportunity::AllowBreak,
-                // Soft breaks after CJK chars (simplified)
-                c if is_cjk(c) => BreakOpportunity::AllowBreak,
-                // Soft breaks after hyphens
-                '-' => BreakOpportunity::AllowBreak,
-                // No break by default
-                _ => BreakOpportunity::NoBreak,
-            };
-            
-            if opp != BreakOpportunity::NoBreak {
-                breaks.push((i + c.len_utf8(), opp));
+                breaks.sort_by_key(|(pos, _)| *pos);
             }
         }
-        
         breaks
     }
     
-    /// Split text into lines that fit within max_width
-    pub fn break_lines(
+    /// Split text into lines that fit within max_width, hyphenating words
+    /// that do not fit
+    pub fn break_lines_hyphenated(
         text: &str,
         max_width: f32,
+        hyphens: Hyphens,
+        lang: Option<&str>,
         mut measure_fn: impl FnMut(&str) -> f32,
-    ) -> Vec<(usize, usize)> {
+    ) -> Vec<BrokenLine> {
+        let mut lines = Vec::new();
         if text.is_empty() {
-            return Vec::new();
+            return lines;
         }
         
-        let breaks = Self::break_opportunities(text);
-        let mut lines = Vec::new();
+        let breaks = Self::hyphenation_opportunities(text, hyphens, lang);
         let mut line_start = 0;
-        let mut last_break = 0;
+        let mut last_break: Option<(usize, bool)> = None;
         
         for (break_pos, opp) in breaks {
-            let segment = &text[line_start..break_pos];
-            let width = measure_fn(segment);
+            let hyphen = opp == BreakOpportunity::Hyphen;
+            let width = if hyphen {
+                measure_fn(&format!("{}{}", &text[line_start..break_pos], HYPHEN))
+            } else {
+                measure_fn(&text[line_start..break_pos])
+            };
             
-            if width > max_width && last_break > line_start {
+            let fits = width <= max_width;
+            if let Some((end, hyphenated)) = last_break.filter(|(end, _)| !fits && *end > line_start) {
                 // Line would be too long, break at last opportunity
-                lines.push((line_start, last_break));
-                line_start = last_break;
+                lines.push(BrokenLine { start: line_start, end, hyphenated });
+                line_start = end;
             }
             
             if opp == BreakOpportunity::MustBreak {
-                lines.push((line_start, break_pos));
+                lines.push(BrokenLine { start: line_start, end: break_pos, hyphenated: false });
                 line_start = break_pos;
             }
             
-            last_break = break_pos;
+            last_break = Some((break_pos, hyphen));
         }
         
         // Final line
         if line_start < text.len() {
-            lines.push((line_start, text.len()));
+            lines.push(BrokenLine { start: line_start, end: text.len(), hyphenated: false });
         }
         
         lines
     }
+    
+    /// Split text into lines that fit within max_width
+    pub fn break_lines(
+        text: &str,
+        max_width: f32,
+        measure_fn: impl FnMut(&str) -> f32,
+    ) -> Vec<(usize, usize)> {
+        Self::break_lines_hyphenated(text, max_width, Hyphens::None, None, measure_fn)
+            .into_iter()
+            .map(|line| (line.start, line.end))
+            .collect()
+    }
+}
+
+/// Words of a text with their byte offsets: letters, marks and
+/// apostrophes between letters
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let is_word_char = |c: char| c.is_alphabetic() || c == SOFT_HYPHEN || c == '\'' || c == '\u{2019}' || crate::shaping::is_cluster_extend(c);
+    let mut rest = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        while rest.next_if(|(_, c)| !is_word_char(*c)).is_some() {}
+        let (start, _) = *rest.peek()?;
+        let mut end = start;
+        while let Some((i, c)) = rest.next_if(|(_, c)| is_word_char(*c)) {
+            end = i + c.len_utf8();
+        }
+        Some((start, text[start..end].trim_matches(['\'', '\u{2019}'])))
+    })
 }
 
-/// Check if character is CJK (simplified check)
-fn is_cjk(c: char) -> bool {
-    let code = c as u32;
-    // CJK Unified Ideographs
-    (0x4E00..=0x9FFF).contains(&code) ||
-    // Hiragana
-    (0x3040..=0x309F).contains(&code) ||
-    // Katakana
-    (0x30A0..=0x30FF).contains(&code) ||
-    // Hangul Syllables
-    (0xAC00..=0xD7AF).contains(&code)
+/// Emoji that take a skin tone modifier
+fn is_emoji_base(c: char) -> bool {
+    matches!(c as u32,
+        0x261D | 0x26F9 | 0x270A..=0x270D | 0x1F385 | 0x1F3C2..=0x1F3C4 | 0x1F3C7 | 0x1F3CA..=0x1F3CC |
+        0x1F442..=0x1F443 | 0x1F446..=0x1F450 | 0x1F466..=0x1F478 | 0x1F47C | 0x1F481..=0x1F483 |
+        0x1F485..=0x1F487 | 0x1F4AA | 0x1F574..=0x1F575 | 0x1F57A | 0x1F590 | 0x1F595..=0x1F596 |
+        0x1F645..=0x1F647 | 0x1F64B..=0x1F64F | 0x1F6A3 | 0x1F6B4..=0x1F6B6 | 0x1F6C0 | 0x1F6CC |
+        0x1F90C | 0x1F90F | 0x1F918..=0x1F91F | 0x1F926 | 0x1F930..=0x1F939 | 0x1F93C..=0x1F93E |
+        0x1F977 | 0x1F9B5..=0x1F9B6 | 0x1F9B8..=0x1F9B9 | 0x1F9BB | 0x1F9CD..=0x1F9CF | 0x1F9D1..=0x1F9DD)
+}
+
+/// Combining marks, controls and the marks of South East Asian scripts,
+/// which attach to the preceding character
+fn is_combining(c: char) -> bool {
+    (crate::shaping::is_cluster_extend(c) && c != '\u{200D}')
+        || c.is_control()
+        || matches!(c as u32, 0x0E31 | 0x0E34..=0x0E3A | 0x0E47..=0x0E4E | 0x0EB1 | 0x0EB4..=0x0EBC | 0x0EC8..=0x0ECD |
+            0x102B..=0x103E | 0x17B4..=0x17D3 | 0xE0020..=0xE007F)
+}
+
+/// Ideographs, kana, fullwidth forms and pictographs, which allow a break
+/// on either side
+fn is_ideographic_break(c: char) -> bool {
+    is_ideographic(c)
+        || is_emoji(c)
+        || matches!(c as u32, 0x3040..=0x30FF | 0x3130..=0x318F | 0x31F0..=0x31FF | 0x3200..=0x33FF | 0xA000..=0xA4CF |
+            0xFE30..=0xFE4F | 0xFF01..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F000..=0x1FAFF)
+}
+
+/// East Asian Wide and Fullwidth characters
+fn is_wide(c: char) -> bool {
+    matches!(c as u32, 0x1100..=0x115F | 0x2E80..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F |
+        0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F | 0x1F900..=0x1F9FF | 0x20000..=0x3FFFD)
 }
 
 #[cfg(test)]
@@ -115,6 +417,44 @@ mod tests {
         assert_eq!(breaks[0].0, 6); // After "hello "
     }
     
+    #[test]
+    fn test_hyphenation() {
+        let text = "a hyphenation example";
+        // Every char is 1 unit wide
+        let lines = LineBreaker::break_lines_hyphenated(text, 8.0, Hyphens::Auto, Some("en"), |s| s.chars().count() as f32);
+        assert_eq!(&text[lines[0].start..lines[0].end], "a hy");
+        assert!(lines[0].hyphenated);
+        assert_eq!(&text[lines[1].start..lines[1].end], "phen");
+        assert!(lines.iter().all(|l| l.end > l.start));
+        
+        // Soft hyphens are opportunities unless hyphens is none
+        let manual = LineBreaker::hyphenation_opportunities("co\u{AD}op", Hyphens::Manual, None);
+        assert_eq!(manual, vec![(4, BreakOpportunity::Hyphen)]);
+        assert!(LineBreaker::hyphenation_opportunities("co\u{AD}op", Hyphens::None, None).is_empty());
+    }
+    
+    #[test]
+    fn test_uax14_rules() {
+        let text = "Price: $3,000.50 (approx.) 日本語。Yes";
+        let lines: Vec<&str> = {
+            let mut start = 0;
+            let mut parts = Vec::new();
+            for (pos, _) in LineBreaker::break_opportunities(text) {
+                parts.push(&text[start..pos]);
+                start = pos;
+            }
+            parts.push(&text[start..]);
+            parts
+        };
+        // Numbers stay whole, punctuation stays with its text, and the
+        // ideographic full stop does not start a line
+        assert_eq!(lines, ["Price: ", "$3,000.50 ", "(approx.) ", "日", "本", "語。", "Yes"]);
+
+        // No break before a combining mark or inside a no-break space
+        assert!(LineBreaker::break_opportunities("e\u{301} x").iter().all(|&(pos, _)| pos == 4));
+        assert!(LineBreaker::break_opportunities("10\u{A0}km").is_empty());
+    }
+    
     #[test]
     fn test_hard_break() {
         let breaks = LineBreaker::break_opportunities("line1\nline2");
@@ -2,10 +2,14 @@
 
 mod line;
 mod paragraph;
+mod justify;
 pub mod vertical;
+pub mod hyphenation;
 
-pub use line::LineBreaker;
-pub use paragraph::ParagraphLayout;
+pub use line::{LineBreaker, BreakOpportunity, BrokenLine, HYPHEN};
+pub use paragraph::{ParagraphLayout, ParagraphStyle};
+pub use hyphenation::{Hyphenator, Hyphens};
+pub use justify::{TextJustify, justification_opportunities};
 pub use vertical::{WritingMode, Direction, TextOrientation, GlyphRotation, VerticalTextContext};
 
 /// Text alignment
@@ -29,6 +33,21 @@ pub struct TextLine {
     pub width: f32,
     /// X offset for alignment
     pub x_offset: f32,
+    /// The line ends at a hyphenation point and is drawn with a hyphen
+    pub hyphenated: bool,
+    /// Space added at each justification opportunity
+    pub justification_gap: f32,
+    /// Byte offsets in the original text of the justification
+    /// opportunities, in order
+    pub justification_opportunities: Vec<usize>,
+}
+
+impl TextLine {
+    /// Horizontal shift from justification for the content at byte offset
+    /// `index`
+    pub fn justification_offset(&self, index: usize) -> f32 {
+        self.justification_gap * self.justification_opportunities.partition_point(|&o| o <= index) as f32
+    }
 }
 
 /// Complete text layout result
@@ -1,6 +1,7 @@
 //! Paragraph layout
 
-use super::{TextAlign, TextLine, TextLayout, LineBreaker};
+use super::{TextAlign, TextLine, TextLayout, LineBreaker, Hyphens, HYPHEN};
+use super::justify::{ends_with_forced_break, justification_opportunities, TextJustify};
 use crate::shaping::TextShaper;
 use crate::font::{FontDatabase, FontId};
 use crate::Result;
@@ -14,8 +15,14 @@ pub struct ParagraphStyle {
     pub line_height: f32,
     /// Text alignment
     pub align: TextAlign,
+    /// CSS `text-justify`, for justified text
+    pub justify: TextJustify,
     /// Font size
     pub font_size: f32,
+    /// CSS `hyphens`
+    pub hyphens: Hyphens,
+    /// Content language (`lang` attribute), for hyphenation dictionaries
+    pub lang: Option<String>,
 }
 
 impl Default for ParagraphStyle {
@@ -24,7 +31,10 @@ impl Default for ParagraphStyle {
             max_width: f32::INFINITY,
             line_height: 1.2,
             align: TextAlign::Left,
+            justify: TextJustify::default(),
             font_size: 16.0,
+            hyphens: Hyphens::default(),
+            lang: None,
         }
     }
 }
@@ -59,12 +69,30 @@ impl ParagraphLayout {
         self
     }
     
+    /// Set justification method
+    pub fn text_justify(mut self, justify: TextJustify) -> Self {
+        self.style.justify = justify;
+        self
+    }
+    
     /// Set font size
     pub fn font_size(mut self, size: f32) -> Self {
         self.style.font_size = size;
         self
     }
     
+    /// Set hyphenation
+    pub fn hyphens(mut self, hyphens: Hyphens) -> Self {
+        self.style.hyphens = hyphens;
+        self
+    }
+    
+    /// Set content language
+    pub fn lang(mut self, lang: &str) -> Self {
+        self.style.lang = Some(lang.to_string());
+        self
+    }
+    
     /// Layout text
     pub fn layout(
         &self,
@@ -98,31 +126,66 @@ impl ParagraphLayout {
         };
         
         // Break into lines
-        let line_ranges = LineBreaker::break_lines(text, self.style.max_width, &mut measure);
+        let line_ranges = LineBreaker::break_lines_hyphenated(
+            text,
+            self.style.max_width,
+            self.style.hyphens,
+            self.style.lang.as_deref(),
+            &mut measure,
+        );
         
         // Build layout
         let mut lines = Vec::new();
         let mut max_width = 0.0f32;
+        let line_count = line_ranges.len();
         
-        for (start, end) in line_ranges {
-            let line_text = &text[start..end].trim_end();
-            let width = shaper.shape(db, font_id, line_text, self.style.font_size)
+        for (i, line) in line_ranges.into_iter().enumerate() {
+            let raw = &text[line.start..line.end];
+            let line_text = raw.trim_end().trim_end_matches('\u{AD}');
+            // Trailing spaces hang and take no justification space
+            let opportunities: Vec<usize> = justification_opportunities(line_text, self.style.justify)
+                .into_iter()
+                .map(|o| line.start + o)
+                .collect();
+            let line_text = if line.hyphenated {
+                format!("{}{}", line_text, HYPHEN)
+            } else {
+                line_text.to_string()
+            };
+            let width = shaper.shape(db, font_id, &line_text, self.style.font_size)
                 .map(|run| run.width())
                 .unwrap_or(0.0);
             max_width = max_width.max(width);
             
             let x_offset = match self.style.align {
-                TextAlign::Left => 0.0,
+                TextAlign::Left | TextAlign::Justify => 0.0,
                 TextAlign::Right => self.style.max_width - width,
                 TextAlign::Center => (self.style.max_width - width) / 2.0,
-                TextAlign::Justify => 0.0, // TODO: Justify needs special handling
+            };
+            
+            // The last line and lines ending in a forced break keep their
+            // natural spacing, as do lines that already overflow
+            let free = self.style.max_width - width;
+            let justify = self.style.align == TextAlign::Justify
+                && i + 1 < line_count
+                && !ends_with_forced_break(raw)
+                && free.is_finite()
+                && free > 0.0
+                && !opportunities.is_empty();
+            let (width, justification_gap, justification_opportunities) = if justify {
+                (self.style.max_width, free / opportunities.len() as f32, opportunities)
+            } else {
+                (width, 0.0, Vec::new())
             };
             
             lines.push(TextLine {
-                start,
-                end,
+                start: line.start,
+                end: line.end,
                 width,
                 x_offset: if self.style.max_width.is_finite() { x_offset } else { 0.0 },
+                hyphenated: line.hyphenated,
+                justification_gap,
+                justification_opportunities,
             });
         }
         
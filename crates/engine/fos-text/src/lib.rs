@@ -3,7 +3,8 @@
 //! This crate provides text rendering for the fOS browser engine:
 //! - Font loading and matching (fontdb)
 //! - Text shaping (rustybuzz - HarfBuzz port)
-//! - Text layout (line breaking, word wrap)
+//! - Text layout (line breaking, word wrap, hyphenation)
+//! - Grapheme, word and sentence segmentation
 //! - Glyph rasterization and caching
 //! - Pre-rendered glyph atlas for ASCII
 //! - Ruby annotations for CJK text
@@ -15,14 +16,16 @@ pub mod render;
 pub mod glyph_atlas;
 pub mod ruby;
 pub mod mmap_resources;
+pub mod segmentation;
 
-pub use font::{FontDatabase, FontFace, FontId, FontStyle, FontWeight, FontQuery};
+pub use font::{FontDatabase, FontFace, FontId, FontStyle, FontWeight, FontStretch, FontQuery, FontMatch};
 pub use shaping::{TextShaper, ShapedGlyph, ShapedRun};
-pub use layout::{TextLayout, LineBreaker, ParagraphLayout};
+pub use layout::{TextLayout, LineBreaker, ParagraphLayout, Hyphens};
 pub use render::{GlyphRasterizer, GlyphAtlas, GlyphKey, RasterizedGlyph};
 pub use glyph_atlas::{GlyphAtlasCache, GlyphInfo};
 pub use ruby::{RubyAnnotation, RubyContainer, RubyStyle};
 pub use mmap_resources::{MappedFont, MappingPool, MappingStats, MappedResource};
+pub use segmentation::{Granularity, Segment};
 
 /// Text rendering error types
 #[derive(Debug, thiserror::Error)]
@@ -12,6 +12,10 @@ pub struct GlyphKey {
     pub glyph_id: u16,
     /// Font size in pixels (quantized to avoid cache explosion)
     pub size_px: u16,
+    /// Hash of the variation coordinates; 0 for the default instance
+    pub variations: u64,
+    /// Text color of color glyphs with foreground layers (RGBA); 0 otherwise
+    pub foreground: u32,
 }
 
 impl GlyphKey {
@@ -22,8 +26,33 @@ impl GlyphKey {
             glyph_id,
             // Quantize to nearest pixel to reduce cache entries
             size_px: font_size.round() as u16,
+            variations: 0,
+            foreground: 0,
         }
     }
+    
+    /// Key for a color glyph painted with a text color
+    pub fn with_foreground(mut self, r: u8, g: u8, b: u8, a: u8) -> Self {
+        self.foreground = u32::from_be_bytes([r, g, b, a]);
+        self
+    }
+    
+    /// Key for a glyph outlined at normalized variation coordinates
+    pub fn with_coords(mut self, coords: &[f32]) -> Self {
+        use std::hash::{Hash, Hasher};
+        
+        self.variations = if coords.iter().all(|&c| c == 0.0) {
+            0
+        } else {
+            // F2Dot14, the precision the coordinates are stored with
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            for &c in coords {
+                ((c * 16384.0).round() as i32).hash(&mut hasher);
+            }
+            hasher.finish() | 1
+        };
+        self
+    }
 }
 
 /// Glyph atlas for caching rasterized glyphs
@@ -137,6 +166,11 @@ mod tests {
         let k1 = GlyphKey::new(0, 65, 16.0);
         let k2 = GlyphKey::new(0, 65, 16.4);  // Same after quantization
         assert_eq!(k1, k2);
+        
+        // The default instance shares entries with static rendering
+        assert_eq!(k1.with_coords(&[0.0, 0.0]), k1);
+        assert_ne!(k1.with_coords(&[0.5]), k1);
+        assert_ne!(k1.with_coords(&[0.5]), k1.with_coords(&[0.75]));
     }
     
     #[test]
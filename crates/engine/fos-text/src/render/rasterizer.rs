@@ -1,8 +1,11 @@
 //! Glyph rasterization
 //!
-//! Uses custom font parser for outline extraction.
+//! Uses custom font parser for outline extraction. Glyphs of variable fonts
+//! are outlined at the normalized axis coordinates of an instance. COLR
+//! color glyphs are filled layer by layer into an RGBA bitmap.
 
-use crate::font::parser::{FontParser, GlyphId, OutlineBuilder};
+use crate::font::parser::{FontParser, GlyphId, GlyphOutline, OutlineBuilder, OutlineCommand};
+use crate::font::{PaletteColor, VariableFontInstance};
 
 /// A rasterized glyph
 #[derive(Debug, Clone)]
@@ -17,8 +20,11 @@ pub struct RasterizedGlyph {
     pub bearing_x: i32,
     /// Y bearing (offset from baseline)
     pub bearing_y: i32,
-    /// Grayscale bitmap (1 byte per pixel)
+    /// Grayscale bitmap (1 byte per pixel), or straight RGBA (4 bytes per
+    /// pixel) if `color` is set
     pub bitmap: Vec<u8>,
+    /// Color glyph, painted as is instead of in the text color
+    pub color: bool,
 }
 
 impl RasterizedGlyph {
@@ -31,6 +37,7 @@ impl RasterizedGlyph {
             bearing_x: 0,
             bearing_y: 0,
             bitmap: Vec::new(),
+            color: false,
         }
     }
 }
@@ -70,21 +77,74 @@ impl GlyphRasterizer {
         
         // Get glyph bounding box
         let bbox = parser.glyph_bounding_box(glyph)?;
+        let bounds = (bbox.x_min as f32, bbox.y_min as f32, bbox.x_max as f32, bbox.y_max as f32);
+        
+        self.fill(glyph_id, parser.units_per_em(), font_size, bounds, |builder| parser.outline_glyph(glyph, builder))
+    }
+    
+    /// Rasterize a glyph of a variable font instance
+    pub fn rasterize_variation(
+        &self,
+        font_data: &[u8],
+        face_index: u32,
+        glyph_id: u16,
+        font_size: f32,
+        instance: &VariableFontInstance,
+    ) -> Option<RasterizedGlyph> {
+        let parser = FontParser::parse_index(font_data, face_index).ok()?;
+        let coords = parser.normalized_coords(instance);
+        self.rasterize_with_coords(&parser, glyph_id, font_size, &coords)
+    }
+    
+    /// Rasterize a glyph at normalized variation coordinates (see
+    /// [`FontParser::normalized_coords`]); the bitmap bounds come from the
+    /// varied outline since `glyf` only has the default bounding box
+    pub fn rasterize_with_coords(
+        &self,
+        parser: &FontParser,
+        glyph_id: u16,
+        font_size: f32,
+        coords: &[f32],
+    ) -> Option<RasterizedGlyph> {
+        if coords.iter().all(|&c| c == 0.0) {
+            return self.rasterize_from_parser(parser, glyph_id, font_size);
+        }
+        
+        let mut outline = GlyphOutline::default();
+        parser.outline_glyph_with_coords(GlyphId(glyph_id), coords, &mut outline)?;
+        let bounds = outline_bounds(&outline)?;
+        
+        self.fill(glyph_id, parser.units_per_em(), font_size, bounds, |builder| {
+            replay(&outline, builder);
+            Some(())
+        })
+    }
+    
+    /// Fill an outline into a grayscale bitmap covering `bounds` (font units)
+    fn fill(
+        &self,
+        glyph_id: u16,
+        units_per_em: u16,
+        font_size: f32,
+        bounds: (f32, f32, f32, f32),
+        outline: impl FnOnce(&mut PathBuilder) -> Option<()>,
+    ) -> Option<RasterizedGlyph> {
+        let (x_min, y_min, x_max, y_max) = bounds;
         
         // Scale factor
-        let scale = font_size / parser.units_per_em() as f32;
+        let scale = font_size / units_per_em as f32;
         
         // Calculate dimensions
-        let width = ((bbox.x_max - bbox.x_min) as f32 * scale).ceil() as u32;
-        let height = ((bbox.y_max - bbox.y_min) as f32 * scale).ceil() as u32;
+        let width = ((x_max - x_min) * scale).ceil() as u32;
+        let height = ((y_max - y_min) * scale).ceil() as u32;
         
         if width == 0 || height == 0 {
             return Some(RasterizedGlyph::empty(glyph_id));
         }
         
         // Create outline builder for tiny-skia
-        let mut builder = PathBuilder::new(scale, bbox.x_min as f32, bbox.y_max as f32);
-        parser.outline_glyph(glyph, &mut builder)?;
+        let mut builder = PathBuilder::new(scale, x_min, y_max);
+        outline(&mut builder)?;
         let path = builder.finish()?;
         
         // Create pixmap
@@ -113,13 +173,117 @@ impl GlyphRasterizer {
             glyph_id,
             width,
             height,
-            bearing_x: (bbox.x_min as f32 * scale) as i32,
-            bearing_y: (bbox.y_max as f32 * scale) as i32,
+            bearing_x: (x_min * scale) as i32,
+            bearing_y: (y_max * scale) as i32,
+            bitmap,
+            color: false,
+        })
+    }
+    
+    /// Rasterize COLR layers (glyph ID and color, bottom first) into one
+    /// RGBA bitmap covering all of them
+    pub fn rasterize_layers(
+        &self,
+        parser: &FontParser,
+        glyph_id: u16,
+        layers: &[(u16, PaletteColor)],
+        font_size: f32,
+    ) -> Option<RasterizedGlyph> {
+        let boxes: Vec<_> = layers.iter()
+            .filter_map(|&(layer, _)| parser.glyph_bounding_box(GlyphId(layer)))
+            .collect();
+        let x_min = boxes.iter().map(|b| b.x_min).min()? as f32;
+        let y_min = boxes.iter().map(|b| b.y_min).min()? as f32;
+        let x_max = boxes.iter().map(|b| b.x_max).max()? as f32;
+        let y_max = boxes.iter().map(|b| b.y_max).max()? as f32;
+        
+        let scale = font_size / parser.units_per_em() as f32;
+        let width = ((x_max - x_min) * scale).ceil() as u32;
+        let height = ((y_max - y_min) * scale).ceil() as u32;
+        if width == 0 || height == 0 {
+            return Some(RasterizedGlyph::empty(glyph_id));
+        }
+        
+        let mut pixmap = tiny_skia::Pixmap::new(width, height)?;
+        for &(layer, color) in layers {
+            let mut builder = PathBuilder::new(scale, x_min, y_max);
+            if parser.outline_glyph(GlyphId(layer), &mut builder).is_none() {
+                continue;
+            }
+            let Some(path) = builder.finish() else { continue };
+            
+            let (r, g, b, a) = color.to_rgba();
+            let mut paint = tiny_skia::Paint::default();
+            paint.set_color_rgba8(r, g, b, a);
+            paint.anti_alias = true;
+            pixmap.fill_path(
+                &path,
+                &paint,
+                tiny_skia::FillRule::Winding,
+                tiny_skia::Transform::identity(),
+                None,
+            );
+        }
+        
+        let bitmap = pixmap.pixels()
+            .iter()
+            .flat_map(|p| {
+                let c = p.demultiply();
+                [c.red(), c.green(), c.blue(), c.alpha()]
+            })
+            .collect();
+        
+        Some(RasterizedGlyph {
+            glyph_id,
+            width,
+            height,
+            bearing_x: (x_min * scale) as i32,
+            bearing_y: (y_max * scale) as i32,
             bitmap,
+            color: true,
         })
     }
 }
 
+/// Bounds of all outline points (on- and off-curve), `None` if empty
+fn outline_bounds(outline: &GlyphOutline) -> Option<(f32, f32, f32, f32)> {
+    let mut bounds: Option<(f32, f32, f32, f32)> = None;
+    let mut add = |x: f32, y: f32| {
+        bounds = Some(match bounds {
+            Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+            None => (x, y, x, y),
+        });
+    };
+    for command in &outline.commands {
+        match *command {
+            OutlineCommand::MoveTo(x, y) | OutlineCommand::LineTo(x, y) => add(x, y),
+            OutlineCommand::QuadTo(x1, y1, x, y) => {
+                add(x1, y1);
+                add(x, y);
+            }
+            OutlineCommand::CurveTo(x1, y1, x2, y2, x, y) => {
+                add(x1, y1);
+                add(x2, y2);
+                add(x, y);
+            }
+            OutlineCommand::Close => {}
+        }
+    }
+    bounds
+}
+
+fn replay(outline: &GlyphOutline, builder: &mut impl OutlineBuilder) {
+    for command in &outline.commands {
+        match *command {
+            OutlineCommand::MoveTo(x, y) => builder.move_to(x, y),
+            OutlineCommand::LineTo(x, y) => builder.line_to(x, y),
+            OutlineCommand::QuadTo(x1, y1, x, y) => builder.quad_to(x1, y1, x, y),
+            OutlineCommand::CurveTo(x1, y1, x2, y2, x, y) => builder.curve_to(x1, y1, x2, y2, x, y),
+            OutlineCommand::Close => builder.close(),
+        }
+    }
+}
+
 impl Default for GlyphRasterizer {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,488 @@
+//! Text Segmentation (UAX #29)
+//!
+//! Grapheme cluster, word and sentence boundaries, used for caret movement,
+//! word selection and `Intl.Segmenter`. Character properties come from
+//! compact range tables covering the scripts the engine renders. Words in
+//! scripts written without spaces (Han, Thai) are not found with a
+//! dictionary: every ideograph is a word of its own.
+
+use crate::font::emoji::{is_emoji, is_skin_tone_modifier};
+use crate::shaping::is_cluster_extend;
+
+/// Segmentation unit, as in `Intl.Segmenter`'s `granularity`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Granularity {
+    #[default]
+    Grapheme,
+    Word,
+    Sentence,
+}
+
+impl Granularity {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "grapheme" => Some(Self::Grapheme),
+            "word" => Some(Self::Word),
+            "sentence" => Some(Self::Sentence),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Grapheme => "grapheme",
+            Self::Word => "word",
+            Self::Sentence => "sentence",
+        }
+    }
+}
+
+/// One segment of a text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment<'a> {
+    /// Start byte offset
+    pub index: usize,
+    pub segment: &'a str,
+    /// For word segments, whether it is a word rather than spaces or
+    /// punctuation; `None` for other granularities
+    pub is_word_like: Option<bool>,
+}
+
+/// Split text into segments
+pub fn segments(text: &str, granularity: Granularity) -> Vec<Segment<'_>> {
+    let boundaries = boundaries(text, granularity);
+    boundaries
+        .windows(2)
+        .map(|w| {
+            let segment = &text[w[0]..w[1]];
+            let is_word_like = (granularity == Granularity::Word).then(|| segment.chars().any(char::is_alphanumeric));
+            Segment { index: w[0], segment, is_word_like }
+        })
+        .collect()
+}
+
+/// Boundary byte offsets, including 0 and `text.len()`
+pub fn boundaries(text: &str, granularity: Granularity) -> Vec<usize> {
+    match granularity {
+        Granularity::Grapheme => grapheme_boundaries(text),
+        Granularity::Word => word_boundaries(text),
+        Granularity::Sentence => sentence_boundaries(text),
+    }
+}
+
+/// Segment containing byte offset `offset`
+pub fn segment_at(text: &str, offset: usize, granularity: Granularity) -> Option<Segment<'_>> {
+    segments(text, granularity).into_iter().find(|s| offset >= s.index && offset < s.index + s.segment.len())
+}
+
+/// Byte range of the word at `offset` (double-click selection); spaces and
+/// punctuation between words select as a unit of their own
+pub fn word_at(text: &str, offset: usize) -> (usize, usize) {
+    // A click past the end selects the last word
+    let offset = offset.min(text.len().saturating_sub(1));
+    segment_at(text, offset, Granularity::Word).map_or((text.len(), text.len()), |s| (s.index, s.index + s.segment.len()))
+}
+
+/// Next boundary after `offset`, or `text.len()`
+pub fn next_boundary(text: &str, offset: usize, granularity: Granularity) -> usize {
+    boundaries(text, granularity).into_iter().find(|&b| b > offset).unwrap_or(text.len())
+}
+
+/// Previous boundary before `offset`, or 0
+pub fn previous_boundary(text: &str, offset: usize, granularity: Granularity) -> usize {
+    boundaries(text, granularity).into_iter().rev().find(|&b| b < offset).unwrap_or(0)
+}
+
+// =============================================================================
+// Grapheme clusters
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GraphemeBreak {
+    Cr,
+    Lf,
+    Control,
+    Extend,
+    Zwj,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    L,
+    V,
+    T,
+    Lv,
+    Lvt,
+    ExtendedPictographic,
+    Other,
+}
+
+fn grapheme_break(c: char) -> GraphemeBreak {
+    use GraphemeBreak::*;
+    match c {
+        '\r' => Cr,
+        '\n' => Lf,
+        '\u{200D}' => Zwj,
+        '\u{1F1E6}'..='\u{1F1FF}' => RegionalIndicator,
+        '\u{0600}'..='\u{0605}' | '\u{06DD}' | '\u{070F}' | '\u{08E2}' | '\u{110BD}' => Prepend,
+        '\u{0903}' | '\u{093B}' | '\u{093E}'..='\u{0940}' | '\u{0949}'..='\u{094C}' | '\u{094E}'..='\u{094F}'
+        | '\u{0982}'..='\u{0983}' | '\u{0E33}' | '\u{0EB3}' => SpacingMark,
+        _ if is_control(c) => Control,
+        _ if is_cluster_extend(c) || is_skin_tone_modifier(c) || matches!(c, '\u{0E31}' | '\u{0E34}'..='\u{0E3A}' | '\u{0E47}'..='\u{0E4E}' | '\u{E0020}'..='\u{E007F}') => Extend,
+        _ => match hangul_type(c) {
+            Some(Hangul::L) => L,
+            Some(Hangul::V) => V,
+            Some(Hangul::T) => T,
+            Some(Hangul::Lv) => Lv,
+            Some(Hangul::Lvt) => Lvt,
+            None if is_extended_pictographic(c) => ExtendedPictographic,
+            None => Other,
+        },
+    }
+}
+
+/// Grapheme cluster boundaries
+pub fn grapheme_boundaries(text: &str) -> Vec<usize> {
+    use GraphemeBreak::*;
+    let mut boundaries = vec![0];
+    let mut prev: Option<GraphemeBreak> = None;
+    // Extended_Pictographic Extend* so far, for emoji Zwj sequences
+    let mut in_pictographic = false;
+    let mut regional_indicators = 0;
+
+    for (i, c) in text.char_indices() {
+        let cur = grapheme_break(c);
+        if let Some(prev) = prev {
+            let keep = match (prev, cur) {
+                (Cr, Lf) => true,
+                (Cr | Lf | Control, _) | (_, Cr | Lf | Control) => false,
+                (L, L | V | Lv | Lvt) | (Lv | V, V | T) | (Lvt | T, T) => true,
+                (_, Extend | Zwj | SpacingMark) | (Prepend, _) => true,
+                (Zwj, ExtendedPictographic) => in_pictographic,
+                (RegionalIndicator, RegionalIndicator) => regional_indicators % 2 == 1,
+                _ => false,
+            };
+            if !keep {
+                boundaries.push(i);
+            }
+        }
+
+        in_pictographic = match cur {
+            ExtendedPictographic => true,
+            Extend => in_pictographic,
+            Zwj => in_pictographic && prev != Some(Zwj),
+            _ => false,
+        };
+        regional_indicators = if cur == RegionalIndicator { regional_indicators + 1 } else { 0 };
+        prev = Some(cur);
+    }
+    if !text.is_empty() {
+        boundaries.push(text.len());
+    }
+    boundaries
+}
+
+// =============================================================================
+// Words
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WordBreak {
+    Cr,
+    Lf,
+    Newline,
+    Extend,
+    Zwj,
+    Format,
+    RegionalIndicator,
+    Katakana,
+    HebrewLetter,
+    ALetter,
+    SingleQuote,
+    DoubleQuote,
+    MidNumLet,
+    MidLetter,
+    MidNum,
+    Numeric,
+    ExtendNumLet,
+    WSegSpace,
+    ExtendedPictographic,
+    Other,
+}
+
+impl WordBreak {
+    fn is_ah_letter(self) -> bool {
+        matches!(self, Self::ALetter | Self::HebrewLetter)
+    }
+
+    /// Skipped by WB4
+    fn is_ignorable(self) -> bool {
+        matches!(self, Self::Extend | Self::Format | Self::Zwj)
+    }
+}
+
+fn word_break(c: char) -> WordBreak {
+    use WordBreak::*;
+    match c {
+        '\r' => Cr,
+        '\n' => Lf,
+        '\u{000B}' | '\u{000C}' | '\u{0085}' | '\u{2028}' | '\u{2029}' => Newline,
+        '\u{200D}' => Zwj,
+        '\u{00AD}' | '\u{0600}'..='\u{0605}' | '\u{061C}' | '\u{06DD}' | '\u{070F}' | '\u{200E}' | '\u{200F}'
+        | '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{2064}' | '\u{2066}'..='\u{206F}' | '\u{FEFF}' | '\u{FFF9}'..='\u{FFFB}' => Format,
+        '\u{1F1E6}'..='\u{1F1FF}' => RegionalIndicator,
+        '\u{3031}'..='\u{3035}' | '\u{309B}' | '\u{309C}' | '\u{30A0}'..='\u{30FA}' | '\u{30FC}'..='\u{30FF}'
+        | '\u{31F0}'..='\u{31FF}' | '\u{32D0}'..='\u{32FE}' | '\u{3300}'..='\u{3357}' | '\u{FF66}'..='\u{FF9D}' => Katakana,
+        '\'' => SingleQuote,
+        '"' => DoubleQuote,
+        '.' | '\u{2018}' | '\u{2019}' | '\u{2024}' | '\u{FE52}' | '\u{FF07}' | '\u{FF0E}' => MidNumLet,
+        ':' | '\u{00B7}' | '\u{0387}' | '\u{055F}' | '\u{05F4}' | '\u{2027}' | '\u{FE13}' | '\u{FE55}' | '\u{FF1A}' => MidLetter,
+        ',' | ';' | '\u{037E}' | '\u{0589}' | '\u{060C}' | '\u{060D}' | '\u{066C}' | '\u{07F8}' | '\u{2044}'
+        | '\u{FE10}' | '\u{FE14}' | '\u{FE50}' | '\u{FE54}' | '\u{FF0C}' | '\u{FF1B}' => MidNum,
+        '_' | '\u{202F}' | '\u{203F}' | '\u{2040}' | '\u{2054}' | '\u{FE33}' | '\u{FE34}' | '\u{FE4D}'..='\u{FE4F}' | '\u{FF3F}' => ExtendNumLet,
+        ' ' | '\u{1680}' | '\u{2000}'..='\u{2006}' | '\u{2008}'..='\u{200A}' | '\u{205F}' | '\u{3000}' => WSegSpace,
+        '\u{FF10}'..='\u{FF19}' => Numeric,
+        _ if is_decimal_digit(c) => Numeric,
+        _ if is_hebrew_letter(c) => HebrewLetter,
+        _ if is_cluster_extend(c) || is_skin_tone_modifier(c) => Extend,
+        _ if is_extended_pictographic(c) => ExtendedPictographic,
+        _ if c.is_alphabetic() && !is_ideographic(c) && !is_complex_context(c) && !matches!(c, '\u{3040}'..='\u{309F}') => ALetter,
+        _ => Other,
+    }
+}
+
+/// Word boundaries
+pub fn word_boundaries(text: &str) -> Vec<usize> {
+    use WordBreak::*;
+    let chars: Vec<(usize, WordBreak)> = text.char_indices().map(|(i, c)| (i, word_break(c))).collect();
+    // Properties of the chars WB4 does not skip, with their indices in `chars`
+    let significant: Vec<(usize, WordBreak)> = chars
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, p))| !p.is_ignorable())
+        .map(|(j, &(_, p))| (j, p))
+        .collect();
+
+    let mut boundaries = vec![0];
+    // Last significant char before the current position, and the next one
+    let mut prev_significant: Option<usize> = None;
+    let mut next_significant = 0;
+    // Regional indicators ending at the last significant char
+    let mut regional_indicators = 0;
+
+    for j in 1..chars.len() {
+        let (i, cur) = chars[j];
+        let raw_prev = chars[j - 1].1;
+        while next_significant < significant.len() && significant[next_significant].0 < j {
+            let p = significant[next_significant].1;
+            regional_indicators = if p == RegionalIndicator { regional_indicators + 1 } else { 0 };
+            prev_significant = Some(next_significant);
+            next_significant += 1;
+        }
+        let prev = prev_significant.map_or(raw_prev, |k| significant[k].1);
+        let prev2 = prev_significant.and_then(|k| k.checked_sub(1)).map(|k| significant[k].1);
+        let next = significant[next_significant..].iter().find(|&&(sj, _)| sj > j).map(|&(_, p)| p);
+
+        let keep = match (raw_prev, cur) {
+            (Cr, Lf) => true,
+            (Cr | Lf | Newline, _) | (_, Cr | Lf | Newline) => false,
+            (Zwj, ExtendedPictographic) => true,
+            (WSegSpace, WSegSpace) => true,
+            (_, Extend | Format | Zwj) => true,
+            _ => match (prev, cur) {
+                (p, c) if p.is_ah_letter() && c.is_ah_letter() => true,
+                (p, MidLetter) | (p, MidNumLet | SingleQuote) if p.is_ah_letter() && next.is_some_and(WordBreak::is_ah_letter) => true,
+                (MidLetter | MidNumLet | SingleQuote, c) if c.is_ah_letter() && prev2.is_some_and(WordBreak::is_ah_letter) => true,
+                (HebrewLetter, SingleQuote) => true,
+                (HebrewLetter, DoubleQuote) => next == Some(HebrewLetter),
+                (DoubleQuote, HebrewLetter) => prev2 == Some(HebrewLetter),
+                (Numeric, Numeric) => true,
+                (p, Numeric) if p.is_ah_letter() => true,
+                (Numeric, c) if c.is_ah_letter() => true,
+                (MidNum | MidNumLet | SingleQuote, Numeric) => prev2 == Some(Numeric),
+                (Numeric, MidNum | MidNumLet | SingleQuote) => next == Some(Numeric),
+                (Katakana, Katakana) => true,
+                (ALetter | HebrewLetter | Numeric | Katakana | ExtendNumLet, ExtendNumLet) => true,
+                (ExtendNumLet, ALetter | HebrewLetter | Numeric | Katakana) => true,
+                (RegionalIndicator, RegionalIndicator) => regional_indicators % 2 == 1,
+                _ => false,
+            },
+        };
+        if !keep {
+            boundaries.push(i);
+        }
+    }
+    if !text.is_empty() {
+        boundaries.push(text.len());
+    }
+    boundaries
+}
+
+// =============================================================================
+// Sentences
+// =============================================================================
+
+/// Sentence boundaries: after sentence-ending punctuation with any closing
+/// quotes, brackets and spaces, unless a period is followed by a lowercase
+/// letter or digit (abbreviations, decimals), and after paragraph breaks
+pub fn sentence_boundaries(text: &str) -> Vec<usize> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut boundaries = vec![0];
+    let mut j = 0;
+    while j < chars.len() {
+        let c = chars[j].1;
+        j += 1;
+        let is_period = matches!(c, '.' | '\u{FF0E}');
+        let is_terminal = matches!(c, '!' | '?' | '\u{203C}' | '\u{2047}'..='\u{2049}' | '\u{3002}' | '\u{FF01}' | '\u{FF1F}' | '\u{0964}' | '\u{0965}' | '\u{061F}');
+        if is_paragraph_separator(c) {
+            if c == '\r' && chars.get(j).is_some_and(|&(_, n)| n == '\n') {
+                j += 1;
+            }
+        } else if is_period || is_terminal {
+            // Further terminators, closing punctuation, then spaces
+            while chars.get(j).is_some_and(|&(_, n)| matches!(n, '.' | '!' | '?')) {
+                j += 1;
+            }
+            while chars.get(j).is_some_and(|&(_, n)| is_close_punctuation(n)) {
+                j += 1;
+            }
+            let after_close = j;
+            while chars.get(j).is_some_and(|&(_, n)| n.is_whitespace() && !is_paragraph_separator(n)) {
+                j += 1;
+            }
+            match chars.get(j) {
+                Some(&(_, n)) if is_paragraph_separator(n) => {
+                    j += if n == '\r' && chars.get(j + 1).is_some_and(|&(_, n)| n == '\n') { 2 } else { 1 };
+                }
+                // "e.g. this" and "3.14" do not end a sentence
+                Some(&(_, n)) if is_period && (n.is_lowercase() || (after_close == j && n.is_ascii_digit())) => continue,
+                // Nor does a period inside a word, as in "example.com"
+                Some(&(_, n)) if is_period && after_close == j && n.is_alphanumeric() => continue,
+                _ => {}
+            }
+        } else {
+            continue;
+        }
+        if j < chars.len() {
+            boundaries.push(chars[j].0);
+        }
+    }
+    if !text.is_empty() {
+        boundaries.push(text.len());
+    }
+    boundaries
+}
+
+fn is_paragraph_separator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{0085}' | '\u{2028}' | '\u{2029}')
+}
+
+fn is_close_punctuation(c: char) -> bool {
+    matches!(c, '"' | '\'' | ')' | ']' | '}' | '\u{00BB}' | '\u{2019}' | '\u{201D}' | '\u{203A}' | '\u{300D}' | '\u{300F}' | '\u{FF09}')
+}
+
+// =============================================================================
+// Shared character properties
+// =============================================================================
+
+/// Hangul syllable type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Hangul {
+    /// Leading consonant (choseong)
+    L,
+    /// Vowel (jungseong)
+    V,
+    /// Trailing consonant (jongseong)
+    T,
+    /// Precomposed syllable without a trailing consonant
+    Lv,
+    /// Precomposed syllable with a trailing consonant
+    Lvt,
+}
+
+pub(crate) fn hangul_type(c: char) -> Option<Hangul> {
+    match c as u32 {
+        0x1100..=0x115F | 0xA960..=0xA97C => Some(Hangul::L),
+        0x1160..=0x11A7 | 0xD7B0..=0xD7C6 => Some(Hangul::V),
+        0x11A8..=0x11FF | 0xD7CB..=0xD7FB => Some(Hangul::T),
+        code @ 0xAC00..=0xD7A3 if (code - 0xAC00) % 28 == 0 => Some(Hangul::Lv),
+        0xAC00..=0xD7A3 => Some(Hangul::Lvt),
+        _ => None,
+    }
+}
+
+/// Decimal digits (general category Nd) of the main scripts
+pub(crate) fn is_decimal_digit(c: char) -> bool {
+    matches!(c as u32,
+        0x0030..=0x0039 | 0x0660..=0x0669 | 0x06F0..=0x06F9 | 0x07C0..=0x07C9 | 0x0966..=0x096F |
+        0x09E6..=0x09EF | 0x0A66..=0x0A6F | 0x0AE6..=0x0AEF | 0x0B66..=0x0B6F | 0x0BE6..=0x0BEF |
+        0x0C66..=0x0C6F | 0x0CE6..=0x0CEF | 0x0D66..=0x0D6F | 0x0E50..=0x0E59 | 0x0ED0..=0x0ED9 |
+        0x0F20..=0x0F29 | 0x1040..=0x1049 | 0x17E0..=0x17E9 | 0x1810..=0x1819)
+}
+
+pub(crate) fn is_hebrew_letter(c: char) -> bool {
+    matches!(c as u32, 0x05D0..=0x05EA | 0x05EF..=0x05F2 | 0xFB1D | 0xFB1F..=0xFB28 | 0xFB2A..=0xFB4F)
+}
+
+/// Han ideographs and CJK symbols
+pub(crate) fn is_ideographic(c: char) -> bool {
+    matches!(c as u32,
+        0x2E80..=0x2FFF | 0x3005..=0x3007 | 0x3021..=0x3029 | 0x3038..=0x303B | 0x3400..=0x4DBF |
+        0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x3FFFD)
+}
+
+/// Scripts written without spaces that need a dictionary to find words
+/// (Thai, Lao, Myanmar, Khmer, Tai Tham)
+pub(crate) fn is_complex_context(c: char) -> bool {
+    matches!(c as u32, 0x0E00..=0x0EFF | 0x1000..=0x109F | 0x1780..=0x17FF | 0x19E0..=0x19FF | 0x1A20..=0x1AAF)
+}
+
+pub(crate) fn is_extended_pictographic(c: char) -> bool {
+    is_emoji(c) && !matches!(c, '\u{FE00}'..='\u{FE0F}' | '\u{1F1E6}'..='\u{1F1FF}') && !is_skin_tone_modifier(c)
+}
+
+fn is_control(c: char) -> bool {
+    matches!(c as u32, 0x00..=0x1F | 0x7F..=0x9F | 0x200B | 0x200E | 0x200F | 0x2028 | 0x2029 | 0x2060..=0x206F | 0xFEFF)
+        && c != '\u{200D}'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(text: &str, granularity: Granularity) -> Vec<&str> {
+        segments(text, granularity).into_iter().map(|s| s.segment).collect()
+    }
+
+    #[test]
+    fn test_graphemes() {
+        // e + combining acute, CRLF, flags, family emoji, Hangul jamo
+        let text = "e\u{301}\r\n\u{1F1EB}\u{1F1F7}\u{1F1E9}\u{1F1EA}\u{1F468}\u{200D}\u{1F469}\u{1F3FD}\u{1100}\u{1161}\u{11A8}";
+        assert_eq!(
+            split(text, Granularity::Grapheme),
+            ["e\u{301}", "\r\n", "\u{1F1EB}\u{1F1F7}", "\u{1F1E9}\u{1F1EA}", "\u{1F468}\u{200D}\u{1F469}\u{1F3FD}", "\u{1100}\u{1161}\u{11A8}"]
+        );
+        assert_eq!(next_boundary("e\u{301}x", 0, Granularity::Grapheme), 3);
+        assert_eq!(previous_boundary("e\u{301}x", 3, Granularity::Grapheme), 0);
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            split("Don't pay $3,000.50 for e-mail_v2 日本語!", Granularity::Word),
+            ["Don't", " ", "pay", " ", "$", "3,000.50", " ", "for", " ", "e", "-", "mail_v2", " ", "日", "本", "語", "!"]
+        );
+        let words = segments("a, b", Granularity::Word);
+        assert_eq!(words.iter().map(|s| s.is_word_like).collect::<Vec<_>>(), [Some(true), Some(false), Some(false), Some(true)]);
+        assert_eq!(word_at("hello world", 8), (6, 11));
+        assert_eq!(word_at("hello world", 5), (5, 6));
+        assert_eq!(Granularity::from_str("sentence"), Some(Granularity::Sentence));
+    }
+
+    #[test]
+    fn test_sentences() {
+        assert_eq!(
+            split("Hello world. It costs 3.50, e.g. cheap!\u{201D} Yes?\nNo", Granularity::Sentence),
+            ["Hello world. ", "It costs 3.50, e.g. cheap!\u{201D} ", "Yes?\n", "No"]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! OpenType Feature Settings
+//!
+//! Maps the CSS `font-feature-settings` and `font-variant-*` properties to
+//! OpenType features with values. A value of 0 turns a feature off, 1 turns
+//! it on and higher values select an alternate glyph.
+
+use super::shaper::Feature;
+
+/// OpenType feature with a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureSetting {
+    pub feature: Feature,
+    pub value: u32,
+}
+
+impl FeatureSetting {
+    pub fn new(feature: Feature, value: u32) -> Self {
+        Self { feature, value }
+    }
+
+    pub fn on(feature: Feature) -> Self {
+        Self::new(feature, 1)
+    }
+
+    pub fn off(feature: Feature) -> Self {
+        Self::new(feature, 0)
+    }
+}
+
+/// Parse a `font-feature-settings` value such as `"liga" 0, "salt" 2`;
+/// `None` if the declaration is invalid
+pub fn parse_font_feature_settings(value: &str) -> Option<Vec<FeatureSetting>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("normal") {
+        return Some(Vec::new());
+    }
+    value.split(',').map(parse_feature_setting).collect()
+}
+
+fn parse_feature_setting(item: &str) -> Option<FeatureSetting> {
+    let item = item.trim();
+    let quote = item.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    let rest = &item[1..];
+    let end = rest.find(quote)?;
+    let feature = Feature::from_str(&rest[..end])?;
+    let value = match rest[end + 1..].trim() {
+        "" | "on" => 1,
+        "off" => 0,
+        number => number.parse().ok()?,
+    };
+    Some(FeatureSetting::new(feature, value))
+}
+
+/// Features for a `font-variant-*` longhand, e.g. `font-variant-caps:
+/// small-caps`; `None` for unknown properties or keywords
+pub fn font_variant_features(property: &str, value: &str) -> Option<Vec<FeatureSetting>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("normal") {
+        return Some(Vec::new());
+    }
+    if property == "font-variant-ligatures" && value.eq_ignore_ascii_case("none") {
+        return Some(
+            [Feature::LIGA, Feature::CLIG, Feature::DLIG, Feature::HLIG, Feature::CALT]
+                .into_iter()
+                .map(FeatureSetting::off)
+                .collect(),
+        );
+    }
+    let mut settings = Vec::new();
+    for keyword in value.split_ascii_whitespace() {
+        let keyword = keyword.to_ascii_lowercase();
+        let (tags, value): (&[&str], u32) = match (property, keyword.as_str()) {
+            ("font-variant-ligatures", "common-ligatures") => (&["liga", "clig"], 1),
+            ("font-variant-ligatures", "no-common-ligatures") => (&["liga", "clig"], 0),
+            ("font-variant-ligatures", "discretionary-ligatures") => (&["dlig"], 1),
+            ("font-variant-ligatures", "no-discretionary-ligatures") => (&["dlig"], 0),
+            ("font-variant-ligatures", "historical-ligatures") => (&["hlig"], 1),
+            ("font-variant-ligatures", "no-historical-ligatures") => (&["hlig"], 0),
+            ("font-variant-ligatures", "contextual") => (&["calt"], 1),
+            ("font-variant-ligatures", "no-contextual") => (&["calt"], 0),
+            ("font-variant-caps", "small-caps") => (&["smcp"], 1),
+            ("font-variant-caps", "all-small-caps") => (&["c2sc", "smcp"], 1),
+            ("font-variant-caps", "petite-caps") => (&["pcap"], 1),
+            ("font-variant-caps", "all-petite-caps") => (&["c2pc", "pcap"], 1),
+            ("font-variant-caps", "unicase") => (&["unic"], 1),
+            ("font-variant-caps", "titling-caps") => (&["titl"], 1),
+            ("font-variant-numeric", "lining-nums") => (&["lnum"], 1),
+            ("font-variant-numeric", "oldstyle-nums") => (&["onum"], 1),
+            ("font-variant-numeric", "proportional-nums") => (&["pnum"], 1),
+            ("font-variant-numeric", "tabular-nums") => (&["tnum"], 1),
+            ("font-variant-numeric", "diagonal-fractions") => (&["frac"], 1),
+            ("font-variant-numeric", "stacked-fractions") => (&["afrc"], 1),
+            ("font-variant-numeric", "ordinal") => (&["ordn"], 1),
+            ("font-variant-numeric", "slashed-zero") => (&["zero"], 1),
+            ("font-variant-position", "sub") => (&["subs"], 1),
+            ("font-variant-position", "super") => (&["sups"], 1),
+            ("font-variant-east-asian", "jis78") => (&["jp78"], 1),
+            ("font-variant-east-asian", "jis83") => (&["jp83"], 1),
+            ("font-variant-east-asian", "jis90") => (&["jp90"], 1),
+            ("font-variant-east-asian", "jis04") => (&["jp04"], 1),
+            ("font-variant-east-asian", "simplified") => (&["smpl"], 1),
+            ("font-variant-east-asian", "traditional") => (&["trad"], 1),
+            ("font-variant-east-asian", "full-width") => (&["fwid"], 1),
+            ("font-variant-east-asian", "proportional-width") => (&["pwid"], 1),
+            ("font-variant-east-asian", "ruby") => (&["ruby"], 1),
+            _ => return None,
+        };
+        settings.extend(tags.iter().filter_map(|tag| Feature::from_str(tag)).map(|f| FeatureSetting::new(f, value)));
+    }
+    Some(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_font_feature_settings() {
+        let settings = parse_font_feature_settings("\"liga\" 0, 'salt' 2, \"smcp\", \"kern\" off").unwrap();
+        assert_eq!(
+            settings,
+            vec![
+                FeatureSetting::off(Feature::LIGA),
+                FeatureSetting::new(Feature(*b"salt"), 2),
+                FeatureSetting::on(Feature(*b"smcp")),
+                FeatureSetting::off(Feature::KERN),
+            ]
+        );
+        assert_eq!(parse_font_feature_settings("normal"), Some(Vec::new()));
+        assert!(parse_font_feature_settings("\"toolong\" 1").is_none());
+        assert!(parse_font_feature_settings("liga").is_none());
+    }
+
+    #[test]
+    fn test_font_variant_features() {
+        let caps = font_variant_features("font-variant-caps", "all-small-caps").unwrap();
+        assert_eq!(caps, vec![FeatureSetting::on(Feature(*b"c2sc")), FeatureSetting::on(Feature(*b"smcp"))]);
+        let numeric = font_variant_features("font-variant-numeric", "tabular-nums slashed-zero").unwrap();
+        assert_eq!(numeric.len(), 2);
+        let none = font_variant_features("font-variant-ligatures", "none").unwrap();
+        assert!(none.iter().all(|s| s.value == 0));
+        assert!(font_variant_features("font-variant-caps", "tabular-nums").is_none());
+    }
+}
@@ -5,7 +5,7 @@
 
 use crate::font::parser::{FontReader, GlyphId};
 use super::memory::BumpAllocator;
-use crate::shaping::gsub::{Coverage, ClassDef};
+use crate::shaping::gsub::{Coverage, ClassDef, feature_lookups};
 
 /// GPOS table processor
 pub struct GposTable<'a> {
@@ -853,9 +853,20 @@ impl<'a> GposTable<'a> {
         reader.read_u16().unwrap_or(0)
     }
     
+    /// Get indices of the lookups a feature uses
+    pub fn feature_lookups(&self, tag: [u8; 4]) -> Vec<u16> {
+        feature_lookups(self.data, self.feature_list_offset, tag)
+    }
+    
     /// Apply kerning between two glyphs
     pub fn get_kerning(&self, first: GlyphId, second: GlyphId) -> Option<i16> {
-        for i in 0..self.lookup_count() {
+        let lookups: Vec<u16> = (0..self.lookup_count()).collect();
+        self.get_kerning_in(&lookups, first, second)
+    }
+    
+    /// Apply kerning between two glyphs using only the given lookups
+    pub fn get_kerning_in(&self, lookups: &[u16], first: GlyphId, second: GlyphId) -> Option<i16> {
+        for &i in lookups {
             if let Some(lookup) = self.get_lookup(i) {
                 if lookup.lookup_type == LookupType::PairAdjustment {
                     for subtable in &lookup.subtables {
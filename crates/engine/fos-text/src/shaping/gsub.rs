@@ -1204,6 +1204,40 @@ impl<'a> GsubTable<'a> {
         let mut reader = FontReader::new(lookup_list_data);
         reader.read_u16().unwrap_or(0)
     }
+    
+    /// Get indices of the lookups a feature uses
+    pub fn feature_lookups(&self, tag: [u8; 4]) -> Vec<u16> {
+        feature_lookups(self.data, self.feature_list_offset, tag)
+    }
+}
+
+/// Lookup indices of every FeatureList record with the given tag, shared by
+/// GSUB and GPOS
+pub(crate) fn feature_lookups(data: &[u8], feature_list_offset: u16, tag: [u8; 4]) -> Vec<u16> {
+    let mut lookups = Vec::new();
+    let Some(feature_list) = data.get(feature_list_offset as usize..) else {
+        return lookups;
+    };
+    let mut reader = FontReader::new(feature_list);
+    let Ok(feature_count) = reader.read_u16() else {
+        return lookups;
+    };
+    for _ in 0..feature_count {
+        let (Ok(feature_tag), Ok(offset)) = (reader.read_tag(), reader.read_u16()) else {
+            break;
+        };
+        if feature_tag != tag {
+            continue;
+        }
+        let Some(feature) = feature_list.get(offset as usize..) else { continue };
+        let mut feature_reader = FontReader::new(feature);
+        let _feature_params = feature_reader.read_u16();
+        let count = feature_reader.read_u16().unwrap_or(0);
+        lookups.extend((0..count).map_while(|_| feature_reader.read_u16().ok()));
+    }
+    lookups.sort_unstable();
+    lookups.dedup();
+    lookups
 }
 
 #[cfg(test)]
@@ -1236,6 +1270,21 @@ mod tests {
         assert_eq!(coverage.get(15), None);
     }
     
+    #[test]
+    fn test_feature_lookups() {
+        // FeatureList with "liga" -> [2, 0] and "kern" -> [1]
+        let data = [
+            0x00, 0x02, // feature count
+            b'l', b'i', b'g', b'a', 0x00, 0x0E,
+            b'k', b'e', b'r', b'n', 0x00, 0x16,
+            0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, // liga
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x01, // kern
+        ];
+        assert_eq!(feature_lookups(&data, 0, *b"liga"), vec![0, 2]);
+        assert_eq!(feature_lookups(&data, 0, *b"kern"), vec![1]);
+        assert!(feature_lookups(&data, 0, *b"smcp").is_empty());
+    }
+    
     #[test]
     fn test_glyph_id() {
         let g = GlyphId(42);
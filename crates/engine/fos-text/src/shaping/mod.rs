@@ -5,6 +5,7 @@
 //! - UAX #9 Bidi algorithm
 //! - Script itemization
 //! - Arabic and Indic complex script shaping
+//! - OpenType features from font-feature-settings and font-variant-*
 
 mod shaper;
 mod run;
@@ -16,8 +17,10 @@ pub mod script;
 pub mod arabic;
 pub mod indic;
 pub mod memory;
+pub mod features;
 
 pub use shaper::{TextShaper, TextDirection, ShaperConfig, Feature};
+pub(crate) use shaper::is_cluster_extend;
 pub use run::{ShapedGlyph, ShapedRun, PositionedGlyph};
 pub use cache::{TextRunCache, TextRunKey, CachedTextRun, TextRunCacheStats};
 pub use gsub::{GsubTable, Coverage, ClassDef, Substitution};
@@ -27,3 +30,4 @@ pub use script::{Script, ScriptItemizer, ScriptRun, Direction, Language};
 pub use arabic::{ArabicShaper, JoiningType, PositionalForm};
 pub use indic::{IndicShaper, IndicCategory, Syllable};
 pub use memory::{BumpAllocator, StringInterner, InternedString};
+pub use features::{FeatureSetting, parse_font_feature_settings, font_variant_features};
@@ -5,6 +5,8 @@
 
 use crate::font::{FontDatabase, FontId};
 use crate::font::parser::{FontParser, GlyphId};
+use crate::font::emoji::{is_emoji_tag, is_regional_indicator, is_skin_tone_modifier, is_zwj};
+use crate::segmentation::is_extended_pictographic;
 use crate::{Result, TextError};
 use super::{ShapedGlyph, ShapedRun};
 use super::gsub::{GsubTable, Substitution};
@@ -13,6 +15,7 @@ use super::bidi::{BidiParagraph, Level};
 use super::script::{Script, ScriptItemizer, ScriptRun, Direction, Language};
 use super::arabic::ArabicShaper;
 use super::indic::IndicShaper;
+use super::features::FeatureSetting;
 
 /// Text direction
 #[derive(Debug, Clone, Copy, Default)]
@@ -48,6 +51,22 @@ impl Feature {
     pub const CCMP: Feature = Feature(*b"ccmp");
     pub const MARK: Feature = Feature(*b"mark");
     pub const MKMK: Feature = Feature(*b"mkmk");
+    pub const CLIG: Feature = Feature(*b"clig");
+    pub const DLIG: Feature = Feature(*b"dlig");
+    pub const HLIG: Feature = Feature(*b"hlig");
+    
+    /// Parse a four-character feature tag
+    pub fn from_str(tag: &str) -> Option<Self> {
+        let bytes: [u8; 4] = tag.as_bytes().try_into().ok()?;
+        bytes.iter().all(|b| (0x20..=0x7E).contains(b)).then_some(Self(bytes))
+    }
+    
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.0).unwrap_or("")
+    }
+    
+    /// Ligature features letter-spacing turns off
+    const OPTIONAL_LIGATURES: [Feature; 5] = [Self::LIGA, Self::CLIG, Self::DLIG, Self::HLIG, Self::CALT];
 }
 
 /// Text shaper configuration
@@ -65,6 +84,41 @@ pub struct ShaperConfig {
     pub no_ligatures: bool,
     /// Disable kerning
     pub no_kerning: bool,
+    /// Feature overrides from font-feature-settings and font-variant-*,
+    /// applied in order after the defaults
+    pub feature_settings: Vec<FeatureSetting>,
+    /// Extra advance after each cluster, in pixels
+    pub letter_spacing: f32,
+    /// Extra advance after each word separator, in pixels
+    pub word_spacing: f32,
+}
+
+impl ShaperConfig {
+    /// Features to apply with their values
+    ///
+    /// Standard ligatures are on and kerning is on unless disabled. A
+    /// non-zero letter-spacing turns optional ligatures off, but explicit
+    /// feature settings still win.
+    pub fn active_features(&self) -> Vec<FeatureSetting> {
+        let mut active: Vec<FeatureSetting> = self.features.iter()
+            .filter(|&&f| !(self.no_ligatures && (f == Feature::LIGA || f == Feature::CLIG)))
+            .filter(|&&f| !(self.no_kerning && f == Feature::KERN))
+            .filter(|f| self.letter_spacing == 0.0 || !Feature::OPTIONAL_LIGATURES.contains(f))
+            .map(|&f| FeatureSetting::on(f))
+            .collect();
+        for setting in &self.feature_settings {
+            active.retain(|s| s.feature != setting.feature);
+            if setting.value != 0 {
+                active.push(*setting);
+            }
+        }
+        active
+    }
+    
+    /// Value of a feature, 0 if it is off
+    pub fn feature_value(&self, feature: Feature) -> u32 {
+        self.active_features().iter().find(|s| s.feature == feature).map_or(0, |s| s.value)
+    }
 }
 
 impl Default for ShaperConfig {
@@ -85,6 +139,9 @@ impl Default for ShaperConfig {
             ],
             no_ligatures: false,
             no_kerning: false,
+            feature_settings: Vec::new(),
+            letter_spacing: 0.0,
+            word_spacing: 0.0,
         }
     }
 }
@@ -142,6 +199,30 @@ impl TextShaper {
         self
     }
     
+    /// Set a feature value, as in `font-feature-settings`
+    pub fn feature(mut self, feature: Feature, value: u32) -> Self {
+        self.config.feature_settings.push(FeatureSetting::new(feature, value));
+        self
+    }
+    
+    /// Add feature settings, later ones overriding earlier ones
+    pub fn feature_settings(mut self, settings: &[FeatureSetting]) -> Self {
+        self.config.feature_settings.extend_from_slice(settings);
+        self
+    }
+    
+    /// Set letter-spacing in pixels
+    pub fn letter_spacing(mut self, spacing: f32) -> Self {
+        self.config.letter_spacing = spacing;
+        self
+    }
+    
+    /// Set word-spacing in pixels
+    pub fn word_spacing(mut self, spacing: f32) -> Self {
+        self.config.word_spacing = spacing;
+        self
+    }
+    
     /// Shape text using a font from the database
     pub fn shape(
         &mut self,
@@ -167,14 +248,15 @@ impl TextShaper {
         let font = FontParser::parse_index(font_data, face_index)
             .map_err(|_| TextError::FontParsing("Failed to parse font".into()))?;
         
-        // Map characters to glyphs
+        // Map characters to glyphs; combining marks join the cluster of
+        // their base
         let mut glyphs: Vec<GlyphInfo> = text.chars()
-            .enumerate()
-            .map(|(i, c)| {
+            .zip(assign_clusters(text))
+            .map(|(c, cluster)| {
                 let glyph_id = font.glyph_index(c).unwrap_or(GlyphId(0));
                 GlyphInfo {
                     glyph_id,
-                    cluster: i as u32,
+                    cluster,
                     char_code: c,
                     x_advance: font.glyph_hor_advance(glyph_id).unwrap_or(0) as i32,
                     y_advance: 0,
@@ -206,11 +288,19 @@ impl TextShaper {
             self.apply_gsub(gsub_data, &mut glyphs);
         }
         
+        // Joiners, selectors and tags left over from emoji sequences the
+        // font has no ligature for are invisible
+        glyphs.retain(|g| !is_default_ignorable(g.char_code));
+        
         // Apply GPOS positioning
         if let Some(gpos_data) = font.table_data(b"GPOS") {
             self.apply_gpos(gpos_data, &mut glyphs, &font);
         }
         
+        // Spacing goes after shaping so it does not break up ligatures
+        let units_per_px = font.units_per_em() as f32 / font_size.max(f32::EPSILON);
+        self.apply_spacing(&mut glyphs, units_per_px);
+        
         // Convert to ShapedGlyph
         let shaped_glyphs: Vec<ShapedGlyph> = glyphs.into_iter()
             .map(|g| ShapedGlyph {
@@ -293,17 +383,26 @@ impl TextShaper {
             None => return,
         };
         
-        // Apply lookups
-        for i in 0..gsub.lookup_count() {
+        // Lookups of the active features, in lookup list order
+        let mut lookups: Vec<(u16, u32)> = self.config.active_features().iter()
+            .flat_map(|s| gsub.feature_lookups(s.feature.0).into_iter().map(move |i| (i, s.value)))
+            .collect();
+        lookups.sort_by_key(|&(i, _)| i);
+        lookups.dedup_by_key(|&mut (i, _)| i);
+        
+        for (i, value) in lookups {
             if let Some(lookup) = gsub.get_lookup(i) {
-                self.apply_gsub_lookup(&lookup, glyphs);
+                self.apply_gsub_lookup(&lookup, glyphs, value);
             }
         }
     }
     
     /// Apply a single GSUB lookup
-    fn apply_gsub_lookup(&self, lookup: &super::gsub::GsubLookup, glyphs: &mut Vec<GlyphInfo>) {
-        use super::gsub::{GsubSubtable, LookupType};
+    ///
+    /// `value` is the feature value; alternate substitutions pick the
+    /// alternate it numbers, starting at 1.
+    fn apply_gsub_lookup(&self, lookup: &super::gsub::GsubLookup, glyphs: &mut Vec<GlyphInfo>, value: u32) {
+        use super::gsub::GsubSubtable;
         
         let mut i = 0;
         while i < glyphs.len() {
@@ -315,6 +414,13 @@ impl TextShaper {
                         }
                     }
                     
+                    GsubSubtable::Alternate(alternate) => {
+                        let index = value.saturating_sub(1) as usize;
+                        if let Substitution::Single(new_id) = alternate.apply(glyphs[i].glyph_id, index) {
+                            glyphs[i].glyph_id = new_id;
+                        }
+                    }
+                    
                     GsubSubtable::Multiple(multiple) => {
                         if let Substitution::Multiple(new_ids) = multiple.apply(glyphs[i].glyph_id) {
                             if !new_ids.is_empty() {
@@ -363,12 +469,16 @@ impl TextShaper {
         };
         
         // Apply kerning
-        if !self.config.no_kerning && glyphs.len() >= 2 {
+        let kern_lookups = match self.config.feature_value(Feature::KERN) {
+            0 => Vec::new(),
+            _ => gpos.feature_lookups(Feature::KERN.0),
+        };
+        if !kern_lookups.is_empty() && glyphs.len() >= 2 {
             for i in 0..glyphs.len() - 1 {
                 let first = glyphs[i].glyph_id;
                 let second = glyphs[i + 1].glyph_id;
                 
-                if let Some(kern) = gpos.get_kerning(first, second) {
+                if let Some(kern) = gpos.get_kerning_in(&kern_lookups, first, second) {
                     glyphs[i].x_advance += kern as i32;
                 }
             }
@@ -377,6 +487,74 @@ impl TextShaper {
         // Apply mark positioning would be done here
         // For now, we apply basic adjustments from lookups
     }
+    
+    /// Apply letter-spacing after every cluster and word-spacing after word
+    /// separators, so ligatures and marks keep their glyphs together
+    fn apply_spacing(&self, glyphs: &mut [GlyphInfo], units_per_px: f32) {
+        let letter = (self.config.letter_spacing * units_per_px).round() as i32;
+        let word = (self.config.word_spacing * units_per_px).round() as i32;
+        if letter == 0 && word == 0 {
+            return;
+        }
+        for i in 0..glyphs.len() {
+            let cluster_end = glyphs.get(i + 1).is_none_or(|next| next.cluster != glyphs[i].cluster);
+            if !cluster_end {
+                continue;
+            }
+            glyphs[i].x_advance += letter;
+            if is_word_separator(glyphs[i].char_code) {
+                glyphs[i].x_advance += word;
+            }
+        }
+    }
+}
+
+/// Characters that stay in the cluster of the preceding character:
+/// combining marks, joiners and variation selectors
+pub(crate) fn is_cluster_extend(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F | 0x0483..=0x0489 | 0x0591..=0x05BD | 0x05BF | 0x05C1..=0x05C2 |
+        0x05C4..=0x05C5 | 0x05C7 | 0x0610..=0x061A | 0x064B..=0x065F | 0x0670 |
+        0x06D6..=0x06DC | 0x06DF..=0x06E4 | 0x06E7..=0x06E8 | 0x06EA..=0x06ED |
+        0x0900..=0x0903 | 0x093A..=0x094F | 0x0951..=0x0957 | 0x0962..=0x0963 |
+        0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x200C..=0x200D | 0x20D0..=0x20FF |
+        0xFE00..=0xFE0F | 0xFE20..=0xFE2F | 0xE0100..=0xE01EF)
+}
+
+/// Cluster of each character (its index in chars). Besides combining
+/// marks, emoji sequences form one cluster so the font can ligate them:
+/// ZWJ sequences, skin tones, tag sequences and regional indicator pairs.
+fn assign_clusters(text: &str) -> Vec<u32> {
+    let mut clusters = Vec::new();
+    let mut cluster = 0;
+    let mut prev: Option<char> = None;
+    let mut open_flag = false;
+    for (i, c) in text.chars().enumerate() {
+        let extends = prev.is_some_and(|p| {
+            is_cluster_extend(c)
+                || is_skin_tone_modifier(c)
+                || is_emoji_tag(c)
+                || (is_zwj(p) && is_extended_pictographic(c))
+                || (is_regional_indicator(c) && open_flag)
+        });
+        if !extends {
+            cluster = i as u32;
+        }
+        open_flag = is_regional_indicator(c) && !open_flag;
+        clusters.push(cluster);
+        prev = Some(c);
+    }
+    clusters
+}
+
+/// Invisible format characters that take no glyph of their own
+fn is_default_ignorable(c: char) -> bool {
+    matches!(c, '\u{200C}' | '\u{200D}' | '\u{FE00}'..='\u{FE0F}' | '\u{E0020}'..='\u{E007F}' | '\u{E0100}'..='\u{E01EF}')
+}
+
+/// Characters word-spacing applies to
+fn is_word_separator(c: char) -> bool {
+    matches!(c, ' ' | '\u{00A0}' | '\u{1361}' | '\u{10100}' | '\u{10101}' | '\u{1039F}' | '\u{1091F}')
 }
 
 impl Default for TextShaper {
@@ -419,5 +597,47 @@ mod tests {
         let config = ShaperConfig::default();
         assert!(!config.no_ligatures);
         assert!(!config.no_kerning);
+        assert_eq!(config.feature_value(Feature::LIGA), 1);
+        assert_eq!(config.feature_value(Feature::KERN), 1);
+    }
+    
+    #[test]
+    fn test_active_features() {
+        let shaper = TextShaper::new()
+            .letter_spacing(2.0)
+            .feature(Feature::KERN, 0)
+            .feature(Feature(*b"salt"), 3)
+            .feature(Feature::DLIG, 1);
+        let config = &shaper.config;
+        assert_eq!(config.feature_value(Feature::LIGA), 0);
+        assert_eq!(config.feature_value(Feature::DLIG), 1);
+        assert_eq!(config.feature_value(Feature::KERN), 0);
+        assert_eq!(config.feature_value(Feature(*b"salt")), 3);
+        
+        let mut glyphs: Vec<GlyphInfo> = "e\u{301} a".chars().zip([0, 0, 2, 3]).map(|(c, cluster)| GlyphInfo {
+            glyph_id: GlyphId(1),
+            cluster,
+            char_code: c,
+            x_advance: 500,
+            y_advance: 0,
+            x_offset: 0,
+            y_offset: 0,
+        }).collect();
+        TextShaper::new().letter_spacing(2.0).word_spacing(4.0).apply_spacing(&mut glyphs, 100.0);
+        let advances: Vec<i32> = glyphs.iter().map(|g| g.x_advance).collect();
+        assert_eq!(advances, vec![500, 700, 1100, 700]);
+        assert_eq!(Feature::from_str("smcp").map(|f| f.0), Some(*b"smcp"));
+    }
+    
+    #[test]
+    fn test_emoji_clusters() {
+        // Family ZWJ sequence, waving hand with skin tone, two flags
+        let text = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}a\u{1F44B}\u{1F3FD}\u{1F1EB}\u{1F1F7}\u{1F1E9}\u{1F1EA}";
+        assert_eq!(assign_clusters(text), vec![0, 0, 0, 0, 0, 5, 6, 6, 8, 8, 10, 10]);
+        
+        // Scotland: black flag with tags; a ZWJ before a letter does not join
+        let text = "\u{1F3F4}\u{E0067}\u{E0062}\u{E0073}\u{E0063}\u{E0074}\u{E007F}\u{200D}x";
+        assert_eq!(assign_clusters(text), vec![0, 0, 0, 0, 0, 0, 0, 0, 8]);
+        assert!(is_default_ignorable('\u{FE0F}') && !is_default_ignorable('\u{1F3F4}'));
     }
 }
@@ -0,0 +1,403 @@
+//! DOM Binding Generation
+//!
+//! Generates the JavaScript glue for DOM interfaces from the descriptions
+//! in [`crate::dom_idl`], written with `dom_interfaces!`: one prototype
+//! object per interface, chained the way the interfaces inherit, with an
+//! accessor property per attribute and a method per operation.
+//!
+//! Arguments and results convert straight between VM values and DOM types
+//! through [`FromJsVal`] and [`IntoJsVal`], without a [`crate::HostValue`]
+//! copy in between. A node is wrapped in one object per realm, created the
+//! first time a script sees it; the wrapper holds nothing but the node's ID,
+//! so every read goes to the live tree.
+//!
+//! Wrappers are cached by node so a node keeps its identity. [`DomBindings::sweep`]
+//! drops the wrappers nothing in the realm can reach any more, except those
+//! carrying properties a script added to a node still in the document.
+
+use crate::JsError;
+use crate::dom_idl::Interface;
+use crate::engine::value::JsVal;
+use crate::engine::vm::{NativeMethod, VirtualMachine};
+use crate::engine::HeapNodeId;
+use fos_dom::{Document, NodeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Generate the `Interface` enum and the glue that defines each interface
+/// on its prototype. Interfaces are listed after the one they inherit from;
+/// attributes name a getter, `fn(&Document, NodeId) -> T`, and for writable
+/// ones a setter, `fn(&mut Document, NodeId, T)`; operations name a
+/// `fn(&mut Document, NodeId, args..) -> Result<T, String>`.
+macro_rules! dom_interfaces {
+    ($(
+        interface $name:ident $(: $parent:ident)? {
+            $(attribute $attr:ident: $aty:ty => $get:path $(, $set:path)?;)*
+            $(operation $op:ident($($arg:ident: $argty:ty),*) -> $ret:ty => $imp:path;)*
+        }
+    )*) => {
+        /// DOM interfaces with generated bindings
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Interface {
+            $($name),*
+        }
+        
+        impl Interface {
+            /// Every interface, each after the one it inherits from
+            pub const ALL: &'static [Interface] = &[$(Interface::$name),*];
+            
+            /// Name of the interface, and of its global
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Interface::$name => stringify!($name)),*
+                }
+            }
+            
+            /// Interface this one inherits from
+            pub fn parent(self) -> Option<Interface> {
+                match self {
+                    $(Interface::$name => None $(.or(Some(Interface::$parent)))?),*
+                }
+            }
+            
+            /// Whether this is `other` or inherits from it
+            pub fn inherits(self, other: Interface) -> bool {
+                std::iter::successors(Some(self), |i| i.parent()).any(|i| i == other)
+            }
+            
+            /// Define the interface's own attributes and operations on its
+            /// prototype
+            pub(crate) fn define(self, vm: &mut $crate::engine::VirtualMachine, proto: $crate::engine::JsVal, bindings: &$crate::bindgen::DomBindings) {
+                match self {
+                    $(Interface::$name => {
+                        $(
+                            let get = $crate::bindgen::getter::<$aty>(bindings, self, $get);
+                            let set = None $(.or(Some($crate::bindgen::setter::<$aty>(bindings, self, $set))))?;
+                            vm.define_accessor(proto, stringify!($attr), Some(get), set);
+                        )*
+                        $(
+                            let b = bindings.clone();
+                            let method: $crate::engine::vm::NativeMethod = std::sync::Arc::new(move |vm, this, args| {
+                                b.with_node(vm, this, self, |cx, node| {
+                                    #[allow(unused_mut, unused_variables)]
+                                    let mut args = args.iter().copied();
+                                    $(
+                                        let $arg = <$argty as $crate::bindgen::FromJsVal>::from_js_val(cx, args.next().unwrap_or($crate::engine::JsVal::Undefined))
+                                            .map_err(|e| format!("{}: {}", stringify!($op), e))?;
+                                    )*
+                                    let result: $ret = $imp(cx.document, node $(, $arg)*)?;
+                                    Ok($crate::bindgen::IntoJsVal::into_js_val(result, cx))
+                                })
+                            });
+                            let method = vm.register_method(method);
+                            vm.set_property(proto, stringify!($op), method);
+                        )*
+                    })*
+                }
+            }
+        }
+    };
+}
+pub(crate) use dom_interfaces;
+
+/// What conversions work with while a binding runs
+pub struct BindingContext<'a> {
+    pub(crate) vm: &'a mut VirtualMachine,
+    pub(crate) document: &'a mut Document,
+    wrappers: &'a mut WrapperCache,
+}
+
+impl BindingContext<'_> {
+    /// The node's wrapper, created on first use
+    pub fn wrap(&mut self, node: NodeId) -> JsVal {
+        if let Some(&obj) = self.wrappers.by_node.get(&node) {
+            return JsVal::Object(obj);
+        }
+        let proto = self.wrappers.prototypes.get(&Interface::of(self.document, node))
+            .copied()
+            .unwrap_or(JsVal::Undefined);
+        let wrapper = self.vm.new_object_with_prototype(proto);
+        if let Some(obj) = wrapper.as_object_id() {
+            self.wrappers.by_node.insert(node, obj);
+            self.wrappers.nodes.insert(obj, node);
+        }
+        wrapper
+    }
+    
+    /// The node a value wraps, if it is a wrapper
+    pub fn unwrap(&self, val: JsVal) -> Option<NodeId> {
+        val.as_object_id().and_then(|obj| self.wrappers.nodes.get(&obj).copied())
+    }
+}
+
+/// Conversion of an argument from JavaScript
+pub trait FromJsVal: Sized {
+    fn from_js_val(cx: &mut BindingContext<'_>, val: JsVal) -> Result<Self, String>;
+}
+
+/// Conversion of a result to JavaScript
+pub trait IntoJsVal {
+    fn into_js_val(self, cx: &mut BindingContext<'_>) -> JsVal;
+}
+
+impl FromJsVal for String {
+    fn from_js_val(_cx: &mut BindingContext<'_>, val: JsVal) -> Result<Self, String> {
+        Ok(val.to_string_val())
+    }
+}
+
+impl FromJsVal for bool {
+    fn from_js_val(_cx: &mut BindingContext<'_>, val: JsVal) -> Result<Self, String> {
+        Ok(val.is_truthy())
+    }
+}
+
+impl FromJsVal for f64 {
+    fn from_js_val(_cx: &mut BindingContext<'_>, val: JsVal) -> Result<Self, String> {
+        Ok(val.to_number())
+    }
+}
+
+impl FromJsVal for NodeId {
+    fn from_js_val(cx: &mut BindingContext<'_>, val: JsVal) -> Result<Self, String> {
+        cx.unwrap(val).ok_or_else(|| "parameter is not of type 'Node'".to_string())
+    }
+}
+
+impl<T: FromJsVal> FromJsVal for Option<T> {
+    fn from_js_val(cx: &mut BindingContext<'_>, val: JsVal) -> Result<Self, String> {
+        if val.is_null() || val.is_undefined() {
+            Ok(None)
+        } else {
+            T::from_js_val(cx, val).map(Some)
+        }
+    }
+}
+
+impl IntoJsVal for () {
+    fn into_js_val(self, _cx: &mut BindingContext<'_>) -> JsVal {
+        JsVal::Undefined
+    }
+}
+
+impl IntoJsVal for String {
+    fn into_js_val(self, _cx: &mut BindingContext<'_>) -> JsVal {
+        JsVal::String(self.into_boxed_str())
+    }
+}
+
+impl IntoJsVal for bool {
+    fn into_js_val(self, _cx: &mut BindingContext<'_>) -> JsVal {
+        JsVal::Bool(self)
+    }
+}
+
+impl IntoJsVal for u32 {
+    fn into_js_val(self, _cx: &mut BindingContext<'_>) -> JsVal {
+        JsVal::Number(self as f64)
+    }
+}
+
+impl IntoJsVal for f64 {
+    fn into_js_val(self, _cx: &mut BindingContext<'_>) -> JsVal {
+        JsVal::Number(self)
+    }
+}
+
+impl IntoJsVal for NodeId {
+    fn into_js_val(self, cx: &mut BindingContext<'_>) -> JsVal {
+        cx.wrap(self)
+    }
+}
+
+impl<T: IntoJsVal> IntoJsVal for Option<T> {
+    fn into_js_val(self, cx: &mut BindingContext<'_>) -> JsVal {
+        match self {
+            Some(value) => value.into_js_val(cx),
+            None => JsVal::Null,
+        }
+    }
+}
+
+/// Accessor getter for an attribute
+pub(crate) fn getter<T: IntoJsVal + 'static>(bindings: &DomBindings, interface: Interface, get: fn(&Document, NodeId) -> T) -> NativeMethod {
+    let bindings = bindings.clone();
+    Arc::new(move |vm, this, _args| {
+        bindings.with_node(vm, this, interface, |cx, node| Ok(get(cx.document, node).into_js_val(cx)))
+    })
+}
+
+/// Accessor setter for a writable attribute
+pub(crate) fn setter<T: FromJsVal + 'static>(bindings: &DomBindings, interface: Interface, set: fn(&mut Document, NodeId, T)) -> NativeMethod {
+    let bindings = bindings.clone();
+    Arc::new(move |vm, this, args| {
+        bindings.with_node(vm, this, interface, |cx, node| {
+            let value = T::from_js_val(cx, args.first().copied().unwrap_or(JsVal::Undefined))?;
+            set(cx.document, node, value);
+            Ok(JsVal::Undefined)
+        })
+    })
+}
+
+/// Wrappers of one realm, both ways, and the interfaces' prototypes
+#[derive(Default)]
+struct WrapperCache {
+    by_node: HashMap<NodeId, u32>,
+    nodes: HashMap<u32, NodeId>,
+    prototypes: HashMap<Interface, JsVal>,
+}
+
+struct BindingState {
+    document: Arc<Mutex<Document>>,
+    wrappers: Mutex<WrapperCache>,
+}
+
+/// The generated DOM bindings installed in one realm
+#[derive(Clone)]
+pub struct DomBindings {
+    state: Arc<BindingState>,
+}
+
+impl DomBindings {
+    /// Define every interface in the VM, with a global per interface
+    /// holding its `prototype`, and `document` wrapping the document node
+    pub fn install(vm: &mut VirtualMachine, document: Arc<Mutex<Document>>) -> Self {
+        let bindings = Self {
+            state: Arc::new(BindingState { document, wrappers: Mutex::new(WrapperCache::default()) }),
+        };
+        
+        for &interface in Interface::ALL {
+            let parent = {
+                let wrappers = bindings.state.wrappers.lock().unwrap();
+                interface.parent().and_then(|p| wrappers.prototypes.get(&p).copied())
+            };
+            let proto = vm.new_object_with_prototype(parent.unwrap_or(JsVal::Undefined));
+            interface.define(vm, proto, &bindings);
+            bindings.state.wrappers.lock().unwrap().prototypes.insert(interface, proto);
+            
+            let global = vm.new_object();
+            vm.set_property(global, "prototype", proto);
+            vm.set_global(interface.name(), global);
+        }
+        
+        let document = bindings.wrap(vm, NodeId::ROOT);
+        vm.set_global("document", document);
+        bindings
+    }
+    
+    /// The node's wrapper, created on first use
+    pub fn wrap(&self, vm: &mut VirtualMachine, node: NodeId) -> JsVal {
+        let mut wrappers = self.state.wrappers.lock().unwrap();
+        let mut document = self.state.document.lock().unwrap();
+        BindingContext { vm, document: &mut document, wrappers: &mut wrappers }.wrap(node)
+    }
+    
+    /// The node a value wraps, if it is one of this realm's wrappers
+    pub fn node(&self, val: JsVal) -> Option<NodeId> {
+        let wrappers = self.state.wrappers.lock().unwrap();
+        val.as_object_id().and_then(|obj| wrappers.nodes.get(&obj).copied())
+    }
+    
+    /// Number of cached wrappers
+    pub fn wrapper_count(&self) -> usize {
+        self.state.wrappers.lock().unwrap().by_node.len()
+    }
+    
+    /// Drop the wrappers the realm can no longer reach, keeping those with
+    /// properties of their own while their node is in the document; returns
+    /// the nodes whose wrappers went
+    pub fn sweep(&self, vm: &VirtualMachine) -> Vec<NodeId> {
+        let reachable = vm.reachable();
+        let mut wrappers = self.state.wrappers.lock().unwrap();
+        let document = self.state.document.lock().unwrap();
+        
+        let kept = |node: NodeId, obj: u32| {
+            reachable.contains(&HeapNodeId::object(obj))
+                || (vm.has_own_properties(JsVal::Object(obj)) && is_connected(&document, node))
+        };
+        let mut released: Vec<NodeId> = wrappers.by_node.iter()
+            .filter(|&(&node, &obj)| !kept(node, obj))
+            .map(|(&node, _)| node)
+            .collect();
+        released.sort_by_key(|node| node.0);
+        
+        for node in &released {
+            if let Some(obj) = wrappers.by_node.remove(node) {
+                wrappers.nodes.remove(&obj);
+            }
+        }
+        released
+    }
+    
+    /// Run a binding on the node `this` wraps; a TypeError if `this` is
+    /// not a wrapper of a node implementing the interface
+    pub(crate) fn with_node(
+        &self,
+        vm: &mut VirtualMachine,
+        this: JsVal,
+        interface: Interface,
+        f: impl FnOnce(&mut BindingContext<'_>, NodeId) -> Result<JsVal, String>,
+    ) -> Result<JsVal, String> {
+        let mut wrappers = self.state.wrappers.lock().unwrap();
+        let mut document = self.state.document.lock().unwrap();
+        let node = this.as_object_id()
+            .and_then(|obj| wrappers.nodes.get(&obj).copied())
+            .filter(|&node| Interface::of(&document, node).inherits(interface))
+            .ok_or_else(|| JsError::TypeError("Illegal invocation".into()).to_string())?;
+        f(&mut BindingContext { vm, document: &mut document, wrappers: &mut wrappers }, node)
+    }
+}
+
+/// Whether a node is in the document's tree
+fn is_connected(document: &Document, node: NodeId) -> bool {
+    std::iter::successors(Some(node), |&n| document.tree.get(n).map(|n| n.parent).filter(|p| p.is_valid()))
+        .any(|n| n == NodeId::ROOT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsContext;
+    
+    #[test]
+    fn test_wrappers_and_prototypes() {
+        let document = Arc::new(Mutex::new(Document::new("https://example.com")));
+        let ctx = JsContext::new(document.clone()).unwrap();
+        
+        // One wrapper per node, inheriting along the interfaces
+        assert_eq!(ctx.eval("document.body === document.body;").unwrap().as_bool(), Some(true));
+        assert_eq!(ctx.eval("document.body.parentNode === document.documentElement;").unwrap().as_bool(), Some(true));
+        assert_eq!(ctx.eval("document.body.appendChild === Node.prototype.appendChild;").unwrap().as_bool(), Some(true));
+        assert_eq!(ctx.eval("document.body.nodeName;").unwrap().as_string(), Some("BODY"));
+        
+        // Wrappers are live views of the tree
+        ctx.exec("p = document.createElement('p'); p.id = 'intro'; p.textContent = 'Hello'; document.body.appendChild(p);").unwrap();
+        {
+            let doc = document.lock().unwrap();
+            let p = doc.get_element_by_id("intro").unwrap();
+            assert_eq!(doc.tree.get(p).unwrap().parent, doc.body());
+        }
+        assert_eq!(ctx.eval("document.getElementById('intro') === p;").unwrap().as_bool(), Some(true));
+        assert_eq!(ctx.eval("p.firstChild.data;").unwrap().as_string(), Some("Hello"));
+        
+        // Members check what `this` is
+        assert!(ctx.eval("p.firstChild.getAttribute = document.body.getAttribute; p.firstChild.getAttribute('id');").is_err());
+        assert!(ctx.eval("p.appendChild(document.body);").is_err());
+    }
+    
+    #[test]
+    fn test_sweep() {
+        let document = Arc::new(Mutex::new(Document::new("https://example.com")));
+        let ctx = JsContext::new(document).unwrap();
+        
+        ctx.exec("kept = document.createElement('div'); lost = document.createElement('span'); lost = null;").unwrap();
+        let released = ctx.sweep_dom_wrappers();
+        assert_eq!(released.len(), 1);
+        assert_eq!(ctx.eval("kept.tagName;").unwrap().as_string(), Some("DIV"));
+        
+        // A wrapper with expandos lives on while its node is in the document
+        ctx.exec("document.body.marker = 1;").unwrap();
+        ctx.sweep_dom_wrappers();
+        assert_eq!(ctx.eval("document.body.marker;").unwrap().as_number(), Some(1.0));
+    }
+}
@@ -1,65 +1,51 @@
 //! DOM Bindings
 //!
-//! JavaScript bindings for DOM manipulation.
+//! The parts of `document` that are not DOM nodes; the node API itself is
+//! generated from interface descriptions, see [`crate::bindgen`].
 
 use crate::{JsValue, JsError};
 use crate::engine_trait::JsContextApi;
-use fos_dom::Document;
+use crate::document_cookie::CookieManager;
+use fos_security::StorageAccessResult;
 use std::sync::{Arc, Mutex};
 
 /// Install document API into global object
-pub fn install_document<C: JsContextApi>(ctx: &C, doc: Arc<Mutex<Document>>) -> Result<(), JsError> {
+pub fn install_document<C: JsContextApi>(
+    ctx: &C,
+    cookies: Arc<Mutex<CookieManager>>,
+) -> Result<(), JsError> {
     let document = ctx.create_object()?;
     
-    // document.getTitle
-    let d = doc.clone();
-    ctx.set_function(&document, "getTitle", move |_args| {
-        let doc = d.lock().unwrap();
-        Ok(JsValue::String(doc.title().to_string()))
+    // document.cookie getter
+    let c = cookies.clone();
+    ctx.set_function(&document, "getCookie", move |_args| {
+        Ok(JsValue::String(c.lock().unwrap().cookie()))
     })?;
     
-    // document.getElementById
-    let d = doc.clone();
-    ctx.set_function(&document, "getElementById", move |args| {
-        if args.is_empty() {
-            return Ok(JsValue::Null);
-        }
-        
-        let id = args[0].as_string().unwrap_or("");
-        let doc = d.lock().unwrap();
-        
-        // Search for element with matching ID
-        if let Some(node_id) = doc.get_element_by_id(id) {
-            Ok(JsValue::Number(node_id.0 as f64))
-        } else {
-            Ok(JsValue::Null)
+    // document.cookie setter
+    let c = cookies.clone();
+    ctx.set_function(&document, "setCookie", move |args| {
+        if let Some(value) = args.first() {
+            c.lock().unwrap().set_cookie(&value.to_string_repr());
         }
+        Ok(JsValue::Undefined)
     })?;
     
-    // document.createElement
-    let d = doc.clone();
-    ctx.set_function(&document, "createElement", move |args| {
-        if args.is_empty() {
-            return Ok(JsValue::Null);
-        }
-        
-        let tag = args[0].as_string().unwrap_or("div");
-        let mut doc = d.lock().unwrap();
-        let node_id = doc.tree.create_element(tag);
-        Ok(JsValue::Number(node_id.0 as f64))
+    // document.hasStorageAccess
+    let c = cookies.clone();
+    ctx.set_function(&document, "hasStorageAccess", move |_args| {
+        Ok(JsValue::Bool(c.lock().unwrap().has_storage_access()))
     })?;
     
-    // document.createTextNode
-    let d = doc.clone();
-    ctx.set_function(&document, "createTextNode", move |args| {
-        if args.is_empty() {
-            return Ok(JsValue::Null);
-        }
-        
-        let text = args[0].as_string().unwrap_or("");
-        let mut doc = d.lock().unwrap();
-        let node_id = doc.tree.create_text(text);
-        Ok(JsValue::Number(node_id.0 as f64))
+    // document.requestStorageAccess: "granted", "denied" or "prompt"
+    let c = cookies.clone();
+    ctx.set_function(&document, "requestStorageAccess", move |_args| {
+        let result = match c.lock().unwrap().request_storage_access() {
+            StorageAccessResult::Granted => "granted",
+            StorageAccessResult::Denied => "denied",
+            StorageAccessResult::Prompt => "prompt",
+        };
+        Ok(JsValue::String(result.to_string()))
     })?;
     
     ctx.set_global("document", JsValue::Object)?;
@@ -76,8 +62,9 @@ mod tests {
     fn test_install_document() {
         let engine = Arc::new(StubEngine::new());
         let ctx = StubContext::new(engine);
-        let doc = Arc::new(Mutex::new(Document::new("test://page")));
+        let jar = Arc::new(Mutex::new(fos_net::CookieJar::new()));
+        let cookies = Arc::new(Mutex::new(CookieManager::new(jar, "test://page")));
         
-        install_document(&ctx, doc).unwrap();
+        install_document(&ctx, cookies).unwrap();
     }
 }
@@ -1,6 +1,6 @@
 //! JavaScript Built-in Objects
 //!
-//! Promise, Map, Set, Symbol, Proxy, BigInt, WeakRef, Atomics.
+//! Promise, Map, Set, Symbol, Proxy, BigInt, WeakRef, Atomics, Intl.Segmenter.
 
 pub mod promise;
 pub mod collections;
@@ -10,6 +10,7 @@ pub mod bigint;
 pub mod weakref;
 pub mod atomics;
 pub mod top_level_await;
+pub mod segmenter;
 
 pub use promise::{JsPromise, PromiseState};
 pub use collections::{JsMap, JsSet, JsWeakMap, JsWeakSet};
@@ -19,3 +20,4 @@ pub use bigint::JsBigInt;
 pub use weakref::{JsWeakRef, FinalizationRegistry};
 pub use atomics::{SharedArrayBuffer, Atomics};
 pub use top_level_await::{AsyncModule, TlaModuleGraph, TlaEvaluationHandle};
+pub use segmenter::{JsSegmenter, JsSegments, SegmentData};
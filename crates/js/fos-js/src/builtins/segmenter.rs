@@ -0,0 +1,180 @@
+//! Intl.Segmenter
+//!
+//! Grapheme, word and sentence segmentation of strings with the Unicode
+//! rules of fos-text. The rules are the same for every locale. Indices are
+//! in UTF-16 code units, as JavaScript strings count them.
+
+use fos_text::segmentation::{segments, Granularity};
+
+/// Intl.Segmenter error types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SegmenterError {
+    RangeError(String),
+}
+
+/// `resolvedOptions()` result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmenterOptions {
+    pub locale: String,
+    pub granularity: &'static str,
+}
+
+/// Intl.Segmenter
+#[derive(Debug, Clone)]
+pub struct JsSegmenter {
+    locale: String,
+    granularity: Granularity,
+}
+
+impl JsSegmenter {
+    /// `new Intl.Segmenter(locales, { granularity })`
+    pub fn new(locales: &[&str], granularity: Option<&str>) -> Result<Self, SegmenterError> {
+        let granularity = match granularity {
+            Some(g) => Granularity::from_str(g).ok_or_else(|| {
+                SegmenterError::RangeError(format!("Value {} out of range for Intl.Segmenter options property granularity", g))
+            })?,
+            None => Granularity::Grapheme,
+        };
+        let supported = Self::supported_locales_of(locales)?;
+        Ok(Self {
+            locale: supported.into_iter().next().unwrap_or_else(|| "en-US".to_string()),
+            granularity,
+        })
+    }
+
+    /// `Intl.Segmenter.supportedLocalesOf()`: every well-formed tag, since
+    /// segmentation does not depend on the locale
+    pub fn supported_locales_of(locales: &[&str]) -> Result<Vec<String>, SegmenterError> {
+        locales
+            .iter()
+            .map(|tag| match canonicalize_tag(tag) {
+                Some(tag) => Ok(tag),
+                None => Err(SegmenterError::RangeError(format!("Incorrect locale information provided: {}", tag))),
+            })
+            .collect()
+    }
+
+    pub fn resolved_options(&self) -> SegmenterOptions {
+        SegmenterOptions { locale: self.locale.clone(), granularity: self.granularity.as_str() }
+    }
+
+    /// `segment(input)`
+    pub fn segment(&self, input: &str) -> JsSegments {
+        let mut index = 0;
+        let mut last = 0;
+        let segments = segments(input, self.granularity)
+            .into_iter()
+            .map(|s| {
+                index += utf16_len(&input[last..s.index]);
+                last = s.index;
+                SegmentData { segment: s.segment.to_string(), index, is_word_like: s.is_word_like }
+            })
+            .collect();
+        JsSegments { input: input.to_string(), segments }
+    }
+}
+
+/// Segment record: `{ segment, index, input, isWordLike }`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentData {
+    pub segment: String,
+    /// Start in UTF-16 code units
+    pub index: usize,
+    /// Only set for word granularity
+    pub is_word_like: Option<bool>,
+}
+
+/// `Segments` object returned by `segment()`
+#[derive(Debug, Clone)]
+pub struct JsSegments {
+    input: String,
+    segments: Vec<SegmentData>,
+}
+
+impl JsSegments {
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// `containing(index)`: the segment containing a UTF-16 index
+    pub fn containing(&self, index: usize) -> Option<&SegmentData> {
+        self.segments.iter().find(|s| index >= s.index && index < s.index + utf16_len(&s.segment))
+    }
+
+    /// Segments in order, as `[Symbol.iterator]()` yields them
+    pub fn iter(&self) -> impl Iterator<Item = &SegmentData> {
+        self.segments.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+}
+
+fn utf16_len(s: &str) -> usize {
+    s.chars().map(char::len_utf16).sum()
+}
+
+/// Case-canonical form of a BCP 47 tag (`en-us` → `en-US`), or `None` if
+/// it is malformed
+fn canonicalize_tag(tag: &str) -> Option<String> {
+    let subtags: Vec<&str> = tag.split(['-', '_']).collect();
+    let language = subtags[0];
+    if !(2..=8).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let mut out = language.to_ascii_lowercase();
+    for subtag in &subtags[1..] {
+        if subtag.is_empty() || subtag.len() > 8 || !subtag.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        out.push('-');
+        match subtag.len() {
+            2 if subtag.chars().all(|c| c.is_ascii_alphabetic()) => out.push_str(&subtag.to_ascii_uppercase()),
+            4 if subtag.chars().all(|c| c.is_ascii_alphabetic()) => {
+                out.push_str(&subtag[..1].to_ascii_uppercase());
+                out.push_str(&subtag[1..].to_ascii_lowercase());
+            }
+            _ => out.push_str(&subtag.to_ascii_lowercase()),
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_segments() {
+        let segmenter = JsSegmenter::new(&["en-us"], Some("word")).unwrap();
+        assert_eq!(segmenter.resolved_options(), SegmenterOptions { locale: "en-US".into(), granularity: "word" });
+
+        // The emoji takes two UTF-16 code units
+        let segments = segmenter.segment("I \u{1F600} it's");
+        let parts: Vec<(&str, usize, Option<bool>)> =
+            segments.iter().map(|s| (s.segment.as_str(), s.index, s.is_word_like)).collect();
+        assert_eq!(
+            parts,
+            [("I", 0, Some(true)), (" ", 1, Some(false)), ("\u{1F600}", 2, Some(false)), (" ", 4, Some(false)), ("it's", 5, Some(true))]
+        );
+        assert_eq!(segments.containing(3).unwrap().segment, "\u{1F600}");
+        assert_eq!(segments.containing(7).unwrap().segment, "it's");
+        assert!(segments.containing(9).is_none());
+    }
+
+    #[test]
+    fn test_options() {
+        let graphemes = JsSegmenter::new(&[], None).unwrap().segment("e\u{301}a");
+        assert_eq!(graphemes.len(), 2);
+        assert_eq!(graphemes.iter().next().unwrap().is_word_like, None);
+
+        assert!(matches!(JsSegmenter::new(&["en"], Some("line")), Err(SegmenterError::RangeError(_))));
+        assert!(JsSegmenter::new(&["not a tag"], None).is_err());
+        assert_eq!(JsSegmenter::supported_locales_of(&["zh-hant-tw"]).unwrap(), ["zh-Hant-TW"]);
+    }
+}
@@ -0,0 +1,241 @@
+//! document.cookie
+//!
+//! Reads and writes the network cookie jar for scripts, through the
+//! security layer's cookie checks. Rejected cookies and defaulted
+//! attributes are logged and kept for the devtools console.
+//!
+//! A document embedded cross-site only sees its cookies once the browser
+//! grants it storage access (`document.requestStorageAccess()`).
+
+use fos_dom::url::{PublicSuffixList, Url};
+use fos_net::CookieJar;
+use fos_net::cookies::parse_set_cookie_for_request;
+use fos_security::{CookieAccess, CookiePolicy, StorageAccess, StorageAccessResult, StorageKey};
+use std::sync::{Arc, Mutex};
+
+/// Cookie state of a document
+pub struct CookieManager {
+    jar: Arc<Mutex<CookieJar>>,
+    /// Document host; empty for opaque origins, which have no cookies
+    host: String,
+    path: String,
+    /// Host of the top-level document, for SameSite
+    site_for_cookies: String,
+    access: CookieAccess,
+    /// Partition of the document, for storage access
+    storage_key: StorageKey,
+    /// Storage access grants shared with the browser
+    storage_access: Option<Arc<Mutex<StorageAccess>>>,
+    /// Transient user activation, consumed by a storage access request
+    user_activation: bool,
+    /// Messages for the devtools console
+    warnings: Vec<String>,
+}
+
+impl CookieManager {
+    /// Cookies of a top-level document at `url`
+    pub fn new(jar: Arc<Mutex<CookieJar>>, url: &str) -> Self {
+        let parsed = Url::parse(url).ok();
+        let host = parsed.as_ref().and_then(|u| u.host_str()).unwrap_or("").to_ascii_lowercase();
+        let path = parsed.as_ref().map(|u| u.path().to_string()).unwrap_or_else(|| "/".to_string());
+        Self {
+            jar,
+            site_for_cookies: host.clone(),
+            host,
+            path,
+            access: CookieAccess::new(url),
+            storage_key: StorageKey::first_party(url),
+            storage_access: None,
+            user_activation: false,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Set the top-level document of a framed document
+    pub fn with_top_level(mut self, top_level_url: &str) -> Self {
+        let top = Url::parse(top_level_url).ok();
+        self.site_for_cookies = top.as_ref().and_then(|u| u.host_str()).unwrap_or("").to_ascii_lowercase();
+        let same_site = PublicSuffixList::current().is_same_site(&self.host, &self.site_for_cookies);
+        self.access = self.access.third_party(!same_site);
+        self.storage_key = StorageKey::new(top_level_url, &self.storage_key.origin);
+        self
+    }
+
+    /// Consult the browser's storage access grants
+    pub fn with_storage_access(mut self, storage_access: Arc<Mutex<StorageAccess>>) -> Self {
+        self.storage_access = Some(storage_access);
+        self
+    }
+
+    /// `document.hasStorageAccess()`
+    pub fn has_storage_access(&self) -> bool {
+        match &self.storage_access {
+            Some(grants) => grants.lock().unwrap().has_storage_access(&self.storage_key),
+            None => !self.storage_key.is_opaque() && !self.storage_key.is_third_party(),
+        }
+    }
+
+    /// The user clicked or typed in the document
+    pub fn notify_user_activation(&mut self) {
+        self.user_activation = true;
+    }
+
+    /// `document.requestStorageAccess()`, which consumes the user
+    /// activation; a prompt is settled by the browser
+    pub fn request_storage_access(&mut self) -> StorageAccessResult {
+        let user_activation = std::mem::take(&mut self.user_activation);
+        let result = match &self.storage_access {
+            Some(grants) => grants.lock().unwrap().request_storage_access(&self.storage_key, user_activation),
+            None if self.has_storage_access() => StorageAccessResult::Granted,
+            None => StorageAccessResult::Denied,
+        };
+        if result == StorageAccessResult::Denied {
+            self.warn(format!("requestStorageAccess() was denied for {}", self.storage_key.origin));
+        }
+        result
+    }
+
+    /// Cookie checks for the document as it stands, with any grant
+    fn access(&self) -> CookieAccess {
+        self.access.clone().with_storage_access(self.has_storage_access())
+    }
+
+    /// Apply a cookie policy
+    pub fn with_policy(mut self, policy: CookiePolicy) -> Self {
+        self.access = self.access.with_policy(policy);
+        self
+    }
+
+    /// Value of `document.cookie`
+    pub fn cookie(&self) -> String {
+        let access = self.access();
+        if self.host.is_empty() || !access.is_allowed() {
+            return String::new();
+        }
+        let jar = self.jar.lock().unwrap();
+        jar.get_cookies(&self.host, &self.path, access.is_secure())
+            .into_iter()
+            .filter(|c| access.can_read(c.http_only, c.secure))
+            .filter(|c| c.same_site_allows(&self.host, &self.site_for_cookies, false))
+            .map(|c| c.serialize())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    /// Assign to `document.cookie`; false if the cookie was rejected
+    pub fn set_cookie(&mut self, value: &str) -> bool {
+        if self.host.is_empty() {
+            return false;
+        }
+        let warnings = match self.access().check_write(value) {
+            Ok(warnings) => warnings,
+            Err(rejection) => {
+                self.warn(rejection.to_string());
+                return false;
+            }
+        };
+        let Some(cookie) = parse_set_cookie_for_request(value, &self.host) else {
+            self.warn(format!("Cookie \"{}\" was rejected: invalid Domain attribute", cookie_name(value)));
+            return false;
+        };
+
+        // Scripts cannot overwrite HttpOnly cookies, nor Secure ones from
+        // an insecure context
+        let mut jar = self.jar.lock().unwrap();
+        let secure = self.access.is_secure();
+        let protected = jar.iter().any(|c| {
+            c.name == cookie.name && c.matches_domain(&self.host) && (c.http_only || (c.secure && !secure))
+        });
+        if protected {
+            drop(jar);
+            self.warn(format!("Cookie \"{}\" was rejected: it would overwrite a protected cookie", cookie.name));
+            return false;
+        }
+        jar.add(cookie);
+        drop(jar);
+
+        for warning in warnings {
+            self.warn(warning.to_string());
+        }
+        true
+    }
+
+    /// Take the messages collected for the devtools console
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    fn warn(&mut self, message: String) {
+        tracing::warn!("[cookies] {}", message);
+        self.warnings.push(message);
+    }
+}
+
+fn cookie_name(value: &str) -> &str {
+    value.split(';').next().and_then(|nv| nv.split_once('=')).map_or("", |(name, _)| name.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_cookie() {
+        let jar = Arc::new(Mutex::new(CookieJar::new()));
+        jar.lock().unwrap().add_from_header("sid=1; HttpOnly", "example.com");
+
+        let mut cookies = CookieManager::new(jar.clone(), "https://example.com/app");
+        assert!(cookies.set_cookie("theme=dark; SameSite=Lax"));
+        assert!(cookies.set_cookie("lang=en"));
+        assert_eq!(cookies.cookie(), "theme=dark; lang=en");
+        assert_eq!(cookies.take_warnings().len(), 1);
+
+        // HttpOnly cookies can be neither set nor overwritten
+        assert!(!cookies.set_cookie("sid=2"));
+        assert!(!cookies.set_cookie("x=1; HttpOnly"));
+        assert_eq!(cookies.take_warnings().len(), 2);
+
+        assert!(cookies.set_cookie("lang=; Max-Age=0"));
+        assert_eq!(cookies.cookie(), "theme=dark");
+    }
+
+    #[test]
+    fn test_insecure_and_cross_site() {
+        let jar = Arc::new(Mutex::new(CookieJar::new()));
+        jar.lock().unwrap().add_from_header("token=1; Secure", "example.com");
+
+        let mut insecure = CookieManager::new(jar.clone(), "http://example.com/");
+        assert_eq!(insecure.cookie(), "");
+        assert!(!insecure.set_cookie("token=2"));
+        assert!(!insecure.set_cookie("a=1; Secure"));
+
+        let mut framed = CookieManager::new(jar, "https://example.com/")
+            .with_top_level("https://news.example.org/")
+            .with_policy(CookiePolicy::AcceptAll);
+        assert!(framed.set_cookie("strict=1; SameSite=Strict"));
+        assert!(framed.set_cookie("none=1; SameSite=None; Secure"));
+        // Only SameSite=None cookies are visible cross-site
+        assert_eq!(framed.cookie(), "none=1");
+    }
+
+    #[test]
+    fn test_storage_access() {
+        let jar = Arc::new(Mutex::new(CookieJar::new()));
+        jar.lock().unwrap().add_from_header("sid=1; SameSite=None; Secure", "comments.net");
+        let grants = Arc::new(Mutex::new(StorageAccess::new()));
+
+        let mut embedded = CookieManager::new(jar, "https://comments.net/embed")
+            .with_top_level("https://news.com/")
+            .with_storage_access(grants.clone());
+        assert!(!embedded.has_storage_access());
+        assert_eq!(embedded.cookie(), "");
+
+        assert_eq!(embedded.request_storage_access(), StorageAccessResult::Denied);
+        embedded.notify_user_activation();
+        assert_eq!(embedded.request_storage_access(), StorageAccessResult::Prompt);
+        let key = StorageKey::new("https://news.com/", "https://comments.net/");
+        grants.lock().unwrap().resolve_prompt(&key, true);
+        assert!(embedded.has_storage_access());
+        assert_eq!(embedded.cookie(), "sid=1");
+    }
+}
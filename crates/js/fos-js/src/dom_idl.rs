@@ -0,0 +1,306 @@
+//! DOM Interface Descriptions
+//!
+//! The DOM interfaces scripts see, described for [`crate::bindgen`], which
+//! generates their bindings, and the fos-dom operations behind them. New
+//! DOM API goes here rather than into hand-written glue.
+
+use crate::bindgen::dom_interfaces;
+use fos_dom::{Document, NodeData, NodeId};
+
+dom_interfaces! {
+    interface Node {
+        attribute nodeType: u32 => node_type;
+        attribute nodeName: String => node_name;
+        attribute parentNode: Option<NodeId> => parent_node;
+        attribute firstChild: Option<NodeId> => first_child;
+        attribute lastChild: Option<NodeId> => last_child;
+        attribute previousSibling: Option<NodeId> => previous_sibling;
+        attribute nextSibling: Option<NodeId> => next_sibling;
+        attribute textContent: Option<String> => text_content, set_text_content;
+        operation appendChild(child: NodeId) -> NodeId => append_child;
+        operation removeChild(child: NodeId) -> NodeId => remove_child;
+        operation hasChildNodes() -> bool => has_child_nodes;
+        operation contains(other: Option<NodeId>) -> bool => contains;
+    }
+    interface Element: Node {
+        attribute tagName: String => tag_name;
+        attribute id: String => id, set_id;
+        attribute className: String => class_name, set_class_name;
+        operation getAttribute(name: String) -> Option<String> => get_attribute;
+        operation setAttribute(name: String, value: String) -> () => set_attribute;
+        operation removeAttribute(name: String) -> () => remove_attribute;
+        operation hasAttribute(name: String) -> bool => has_attribute;
+    }
+    interface Text: Node {
+        attribute data: String => data, set_data;
+    }
+    interface Document: Node {
+        attribute title: String => title;
+        attribute documentElement: Option<NodeId> => document_element;
+        attribute head: Option<NodeId> => head;
+        attribute body: Option<NodeId> => body;
+        operation getElementById(id: String) -> Option<NodeId> => get_element_by_id;
+        operation createElement(tag: String) -> NodeId => create_element;
+        operation createTextNode(data: String) -> NodeId => create_text_node;
+    }
+}
+
+impl Interface {
+    /// Most derived interface a node implements
+    pub(crate) fn of(document: &Document, node: NodeId) -> Interface {
+        match document.tree.get(node).map(|n| &n.data) {
+            Some(NodeData::Document) => Interface::Document,
+            Some(NodeData::Element(_)) => Interface::Element,
+            Some(NodeData::Text(_)) => Interface::Text,
+            _ => Interface::Node,
+        }
+    }
+}
+
+fn valid(node: NodeId) -> Option<NodeId> {
+    node.is_valid().then_some(node)
+}
+
+fn link(doc: &Document, node: NodeId, f: fn(&fos_dom::Node) -> NodeId) -> Option<NodeId> {
+    doc.tree.get(node).map(f).and_then(valid)
+}
+
+fn is_inclusive_ancestor(doc: &Document, ancestor: NodeId, node: NodeId) -> bool {
+    std::iter::successors(Some(node), |&n| link(doc, n, |n| n.parent)).any(|n| n == ancestor)
+}
+
+// Node
+
+fn node_type(doc: &Document, node: NodeId) -> u32 {
+    match doc.tree.get(node).map(|n| &n.data) {
+        Some(NodeData::Element(_)) => 1,
+        Some(NodeData::Text(_)) => 3,
+        Some(NodeData::ProcessingInstruction { .. }) => 7,
+        Some(NodeData::Comment(_)) => 8,
+        Some(NodeData::Document) => 9,
+        Some(NodeData::Doctype { .. }) => 10,
+        None => 0,
+    }
+}
+
+fn node_name(doc: &Document, node: NodeId) -> String {
+    match doc.tree.get(node).map(|n| &n.data) {
+        Some(NodeData::Element(_)) => tag_name(doc, node),
+        Some(NodeData::Text(_)) => "#text".into(),
+        Some(NodeData::Comment(_)) => "#comment".into(),
+        Some(NodeData::Document) => "#document".into(),
+        Some(NodeData::Doctype { name, .. }) => doc.tree.resolve(*name).to_string(),
+        Some(NodeData::ProcessingInstruction { target, .. }) => doc.tree.resolve(*target).to_string(),
+        None => String::new(),
+    }
+}
+
+fn parent_node(doc: &Document, node: NodeId) -> Option<NodeId> {
+    link(doc, node, |n| n.parent)
+}
+
+fn first_child(doc: &Document, node: NodeId) -> Option<NodeId> {
+    link(doc, node, |n| n.first_child)
+}
+
+fn last_child(doc: &Document, node: NodeId) -> Option<NodeId> {
+    link(doc, node, |n| n.last_child)
+}
+
+fn previous_sibling(doc: &Document, node: NodeId) -> Option<NodeId> {
+    link(doc, node, |n| n.prev_sibling)
+}
+
+fn next_sibling(doc: &Document, node: NodeId) -> Option<NodeId> {
+    link(doc, node, |n| n.next_sibling)
+}
+
+/// Text of a character node, or of an element's descendant text nodes;
+/// null for the document
+fn text_content(doc: &Document, node: NodeId) -> Option<String> {
+    fn collect(doc: &Document, node: NodeId, out: &mut String) {
+        for (child, data) in doc.tree.children(node) {
+            match data.as_text() {
+                Some(text) => out.push_str(text),
+                None => collect(doc, child, out),
+            }
+        }
+    }
+    
+    match &doc.tree.get(node)?.data {
+        NodeData::Text(text) => Some(text.content.clone()),
+        NodeData::Comment(data) | NodeData::ProcessingInstruction { data, .. } => Some(data.clone()),
+        NodeData::Element(_) => {
+            let mut out = String::new();
+            collect(doc, node, &mut out);
+            Some(out)
+        }
+        NodeData::Document | NodeData::Doctype { .. } => None,
+    }
+}
+
+/// Replace an element's children with one text node, or a character
+/// node's data
+fn set_text_content(doc: &mut Document, node: NodeId, value: Option<String>) {
+    let value = value.unwrap_or_default();
+    match doc.tree.get(node).map(|n| &n.data) {
+        Some(NodeData::Element(_)) => {
+            let children: Vec<NodeId> = doc.tree.children(node).map(|(child, _)| child).collect();
+            for child in children {
+                doc.tree.remove(child);
+            }
+            if !value.is_empty() {
+                let text = doc.tree.create_text(&value);
+                doc.tree.append_child(node, text);
+            }
+        }
+        Some(NodeData::Text(_) | NodeData::Comment(_)) => doc.tree.set_text(node, &value),
+        _ => {}
+    }
+}
+
+fn append_child(doc: &mut Document, parent: NodeId, child: NodeId) -> Result<NodeId, String> {
+    let can_have_children = matches!(doc.tree.get(parent).map(|n| &n.data), Some(NodeData::Element(_) | NodeData::Document));
+    let insertable = !matches!(doc.tree.get(child).map(|n| &n.data), Some(NodeData::Document) | None);
+    if !can_have_children || !insertable || is_inclusive_ancestor(doc, child, parent) {
+        return Err("HierarchyRequestError: the new child cannot be inserted here".into());
+    }
+    doc.tree.remove(child);
+    doc.tree.append_child(parent, child);
+    Ok(child)
+}
+
+fn remove_child(doc: &mut Document, parent: NodeId, child: NodeId) -> Result<NodeId, String> {
+    if parent_node(doc, child) != Some(parent) {
+        return Err("NotFoundError: the node to be removed is not a child of this node".into());
+    }
+    doc.tree.remove(child);
+    Ok(child)
+}
+
+fn has_child_nodes(doc: &mut Document, node: NodeId) -> Result<bool, String> {
+    Ok(first_child(doc, node).is_some())
+}
+
+fn contains(doc: &mut Document, node: NodeId, other: Option<NodeId>) -> Result<bool, String> {
+    Ok(other.is_some_and(|other| is_inclusive_ancestor(doc, node, other)))
+}
+
+// Element
+
+fn tag_name(doc: &Document, node: NodeId) -> String {
+    doc.tree.get(node)
+        .and_then(|n| n.as_element())
+        .map(|elem| doc.tree.resolve(elem.name.local).to_ascii_uppercase())
+        .unwrap_or_default()
+}
+
+fn attribute(doc: &Document, node: NodeId, name: &str) -> Option<String> {
+    let name = doc.tree.interner().intern_lookup(&name.to_ascii_lowercase())?;
+    doc.tree.get(node)?.as_element()?.get_attr(name).map(str::to_string)
+}
+
+fn id(doc: &Document, node: NodeId) -> String {
+    attribute(doc, node, "id").unwrap_or_default()
+}
+
+fn set_id(doc: &mut Document, node: NodeId, value: String) {
+    doc.tree.set_attribute(node, "id", &value);
+}
+
+fn class_name(doc: &Document, node: NodeId) -> String {
+    attribute(doc, node, "class").unwrap_or_default()
+}
+
+fn set_class_name(doc: &mut Document, node: NodeId, value: String) {
+    doc.tree.set_attribute(node, "class", &value);
+}
+
+fn get_attribute(doc: &mut Document, node: NodeId, name: String) -> Result<Option<String>, String> {
+    Ok(attribute(doc, node, &name))
+}
+
+fn set_attribute(doc: &mut Document, node: NodeId, name: String, value: String) -> Result<(), String> {
+    check_name(&name)?;
+    doc.tree.set_attribute(node, &name.to_ascii_lowercase(), &value);
+    Ok(())
+}
+
+fn remove_attribute(doc: &mut Document, node: NodeId, name: String) -> Result<(), String> {
+    doc.tree.remove_attribute(node, &name.to_ascii_lowercase());
+    Ok(())
+}
+
+fn has_attribute(doc: &mut Document, node: NodeId, name: String) -> Result<bool, String> {
+    Ok(attribute(doc, node, &name).is_some())
+}
+
+/// Reject names the HTML parser could never produce
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.chars().any(|c| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\'' | '/' | '=')) {
+        return Err(format!("InvalidCharacterError: '{}' is not a valid name", name));
+    }
+    Ok(())
+}
+
+// Text
+
+fn data(doc: &Document, node: NodeId) -> String {
+    doc.tree.get(node).and_then(|n| n.as_text()).unwrap_or_default().to_string()
+}
+
+fn set_data(doc: &mut Document, node: NodeId, value: String) {
+    doc.tree.set_text(node, &value);
+}
+
+// Document
+
+fn title(doc: &Document, _node: NodeId) -> String {
+    doc.title()
+}
+
+fn document_element(doc: &Document, _node: NodeId) -> Option<NodeId> {
+    valid(doc.document_element())
+}
+
+fn head(doc: &Document, _node: NodeId) -> Option<NodeId> {
+    valid(doc.head())
+}
+
+fn body(doc: &Document, _node: NodeId) -> Option<NodeId> {
+    valid(doc.body())
+}
+
+fn get_element_by_id(doc: &mut Document, _node: NodeId, id: String) -> Result<Option<NodeId>, String> {
+    Ok(doc.get_element_by_id(&id))
+}
+
+fn create_element(doc: &mut Document, _node: NodeId, tag: String) -> Result<NodeId, String> {
+    check_name(&tag)?;
+    Ok(doc.tree.create_element(&tag.to_ascii_lowercase()))
+}
+
+fn create_text_node(doc: &mut Document, _node: NodeId, data: String) -> Result<NodeId, String> {
+    Ok(doc.tree.create_text(&data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_interfaces() {
+        assert_eq!(Interface::Element.parent(), Some(Interface::Node));
+        assert!(Interface::Document.inherits(Interface::Node));
+        assert!(!Interface::Text.inherits(Interface::Element));
+        
+        let mut doc = Document::new("https://example.com");
+        let p = create_element(&mut doc, NodeId::ROOT, "P".into()).unwrap();
+        set_text_content(&mut doc, p, Some("one".into()));
+        set_text_content(&mut doc, p, Some("two".into()));
+        assert_eq!(tag_name(&doc, p), "P");
+        assert_eq!(text_content(&doc, p).as_deref(), Some("two"));
+        assert_eq!(Interface::of(&doc, first_child(&doc, p).unwrap()), Interface::Text);
+        assert!(append_child(&mut doc, p, NodeId::ROOT).is_err());
+    }
+}
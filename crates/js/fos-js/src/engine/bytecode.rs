@@ -3,6 +3,8 @@
 //! Stack-based bytecode for the JavaScript VM.
 //! Uses compact encoding for common operations.
 
+use super::token::Span;
+
 /// Bytecode instruction
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -52,6 +54,7 @@ pub enum Opcode {
     SetProperty = 31,    // name_idx: u16
     GetIndex = 32,
     SetIndex = 33,
+    SetMember = 34,      // name_idx: u16; pops value and object, pushes value
     
     // Arithmetic
     Add = 40,
@@ -101,6 +104,7 @@ pub enum Opcode {
     ReturnUndefined = 94, // Common: return without value
     Closure = 95,        // const_idx: u16, upvalue_count: u8, then upvalue_info
     TailCall = 96,       // Tail call optimization - reuse stack frame
+    CallMethod = 97,     // argc: u8; receiver below the callee becomes `this`
     
     // Objects
     NewObject = 100,
@@ -161,6 +165,8 @@ pub struct CompiledFunction {
     pub upvalue_count: u8,
     pub upvalues: Vec<UpvalueInfo>,
     pub bytecode: Bytecode,
+    /// Source range of the function
+    pub span: Span,
 }
 
 /// Upvalue capture info
@@ -179,6 +185,7 @@ impl CompiledFunction {
             upvalue_count: 0,
             upvalues: Vec::new(),
             bytecode: Bytecode::new(),
+            span: Span::new(0, 0),
         }
     }
 }
@@ -4,6 +4,7 @@
 
 use super::ast::{Ast, AstNode, AstNodeKind, NodeId, LiteralValue, BinaryOp, VarKind, UnaryOp, LogicalOp, AssignOp};
 use super::bytecode::{Bytecode, Opcode, Constant, CompiledFunction, UpvalueInfo};
+use super::token::Span;
 
 /// Compiler
 pub struct Compiler {
@@ -219,8 +220,17 @@ impl Compiler {
                 self.patch_jump(jump);
             }
             AstNodeKind::AssignmentExpression { left, right, .. } => {
+                // Member targets: object, value, then store
+                if let Some((object, name)) = self.static_member(ast, *left) {
+                    self.compile_node(ast, object)?;
+                    self.compile_node(ast, *right)?;
+                    let idx = self.bytecode.add_name(name);
+                    self.bytecode.emit(Opcode::SetMember);
+                    self.bytecode.emit_u16(idx);
+                    return Ok(());
+                }
+                // SetLocal and SetGlobal leave the value as the result
                 self.compile_node(ast, *right)?;
-                self.bytecode.emit(Opcode::Dup);
                 if let Some(left_node) = ast.get(*left) {
                     if let AstNodeKind::Identifier { name } = &left_node.kind {
                         if let Some(slot) = self.resolve_local(name) {
@@ -235,9 +245,19 @@ impl Compiler {
                 }
             }
             AstNodeKind::CallExpression { callee, arguments } => {
-                self.compile_node(ast, *callee)?;
+                // Method calls keep the object under the callee as `this`
+                let method = self.static_member(ast, *callee);
+                if let Some((object, name)) = method {
+                    self.compile_node(ast, object)?;
+                    self.bytecode.emit(Opcode::Dup);
+                    let idx = self.bytecode.add_name(name);
+                    self.bytecode.emit(Opcode::GetProperty);
+                    self.bytecode.emit_u16(idx);
+                } else {
+                    self.compile_node(ast, *callee)?;
+                }
                 for arg in arguments { self.compile_node(ast, *arg)?; }
-                self.bytecode.emit(Opcode::Call);
+                self.bytecode.emit(if method.is_some() { Opcode::CallMethod } else { Opcode::Call });
                 self.bytecode.emit_u8(arguments.len() as u8);
             }
             AstNodeKind::MemberExpression { object, property, computed, .. } => {
@@ -369,7 +389,7 @@ impl Compiler {
                 });
                 
                 // Compile function body with new compiler
-                let func = self.compile_function(ast, name, params, *body)?;
+                let func = self.compile_function(ast, name, params, *body, node.span)?;
                 
                 // Add compiled function to constants
                 let const_idx = self.bytecode.add_constant(Constant::Function(Box::new(func)));
@@ -397,7 +417,7 @@ impl Compiler {
             }
             AstNodeKind::ArrowFunctionExpression { params, body, .. } => {
                 // Compile arrow function body
-                let func = self.compile_function(ast, None, params, *body)?;
+                let func = self.compile_function(ast, None, params, *body, node.span)?;
                 let const_idx = self.bytecode.add_constant(Constant::Function(Box::new(func)));
                 self.bytecode.emit(Opcode::LoadConst);
                 self.bytecode.emit_u16(const_idx);
@@ -511,7 +531,7 @@ impl Compiler {
     }
     
     /// Compile a function body into a CompiledFunction
-    fn compile_function(&mut self, ast: &Ast, name: Option<Box<str>>, params: &[NodeId], body: NodeId) -> Result<CompiledFunction, String> {
+    fn compile_function(&mut self, ast: &Ast, name: Option<Box<str>>, params: &[NodeId], body: NodeId, span: Span) -> Result<CompiledFunction, String> {
         use super::bytecode::CompiledFunction;
         
         // Create a new compiler for the function body
@@ -541,9 +561,21 @@ impl Compiler {
             upvalue_count: 0, // TODO: Track upvalues
             upvalues: Vec::new(),
             bytecode: func_compiler.bytecode,
+            span,
         })
     }
     
+    /// Object and property name of a `a.b` expression
+    fn static_member<'a>(&self, ast: &'a Ast, id: NodeId) -> Option<(NodeId, &'a str)> {
+        let AstNodeKind::MemberExpression { object, property, computed: false, .. } = &ast.get(id)?.kind else {
+            return None;
+        };
+        match &ast.get(*property)?.kind {
+            AstNodeKind::Identifier { name } => Some((*object, name)),
+            _ => None,
+        }
+    }
+    
     fn emit_jump(&mut self, op: Opcode) -> usize {
         self.bytecode.emit(op);
         self.bytecode.emit_u16(0);
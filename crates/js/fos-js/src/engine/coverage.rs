@@ -0,0 +1,129 @@
+//! Code Coverage
+//!
+//! Execution counters the VM keeps while coverage is on: how often each
+//! function was called and which of its bytecode instructions ran. Functions
+//! are reported by their source byte range, so the DevTools coverage panel
+//! can mark unused parts of a script.
+
+use super::bytecode::{Bytecode, CompiledFunction, Constant};
+use std::collections::HashMap;
+
+/// Coverage of one function, or of a script's top-level code
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCoverage {
+    /// Function name; empty for anonymous functions and top-level code
+    pub name: Box<str>,
+    /// Start of the function in the script source, in bytes
+    pub start: u32,
+    /// End of the function in the script source, in bytes
+    pub end: u32,
+    /// Times the function was called
+    pub count: u64,
+    /// Whether the instruction at each bytecode offset ran
+    executed: Vec<bool>,
+}
+
+impl FunctionCoverage {
+    fn new(name: &str, start: u32, end: u32, bytecode_len: usize) -> Self {
+        Self { name: name.into(), start, end, count: 0, executed: vec![false; bytecode_len] }
+    }
+    
+    /// Length of the function's bytecode
+    pub fn bytecode_len(&self) -> usize {
+        self.executed.len()
+    }
+    
+    /// Bytecode offsets of the instructions that ran
+    pub fn executed_offsets(&self) -> impl Iterator<Item = usize> + '_ {
+        self.executed.iter().enumerate().filter(|(_, ran)| **ran).map(|(offset, _)| offset)
+    }
+    
+    /// Number of distinct instructions that ran
+    pub fn executed_instructions(&self) -> usize {
+        self.executed_offsets().count()
+    }
+}
+
+/// Coverage of one script run by the VM
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptCoverage {
+    /// Script URL; empty for `eval` and timer code
+    pub url: String,
+    /// Length of the source in bytes
+    pub source_len: u32,
+    /// Top-level code first, then every function in source order; ones
+    /// never called have a zero count
+    pub functions: Vec<FunctionCoverage>,
+}
+
+/// Per-function execution counters for all scripts run since coverage
+/// started
+#[derive(Debug, Clone, Default)]
+pub struct CoverageCounters {
+    scripts: Vec<ScriptCoverage>,
+    /// Index into `scripts` by the VM's script ID
+    script_index: HashMap<u32, usize>,
+    /// Function index by script index and source range
+    functions: HashMap<(usize, u32, u32), usize>,
+}
+
+/// A function being counted: script index and function index
+pub type CoverageSlot = (usize, usize);
+
+impl CoverageCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Start counting script `script_id` and the functions it defines;
+    /// returns the slot of its top-level code
+    pub fn begin_script(&mut self, script_id: u32, url: &str, source_len: usize, bytecode: &Bytecode) -> CoverageSlot {
+        let script = self.scripts.len();
+        let source_len = source_len as u32;
+        let mut top_level = FunctionCoverage::new("", 0, source_len, bytecode.len());
+        top_level.count = 1;
+        let mut functions = vec![top_level];
+        collect_functions(bytecode, &mut functions);
+        functions[1..].sort_by_key(|f| (f.start, std::cmp::Reverse(f.end)));
+        for (index, function) in functions.iter().enumerate().skip(1) {
+            self.functions.insert((script, function.start, function.end), index);
+        }
+        
+        self.scripts.push(ScriptCoverage { url: url.to_string(), source_len, functions });
+        self.script_index.insert(script_id, script);
+        (script, 0)
+    }
+    
+    /// Count a call of `function` defined by script `script_id`; `None` for
+    /// scripts that ran before coverage started
+    pub fn enter(&mut self, script_id: u32, function: &CompiledFunction) -> Option<CoverageSlot> {
+        let script = *self.script_index.get(&script_id)?;
+        let index = *self.functions.get(&(script, function.span.start, function.span.end))?;
+        self.scripts[script].functions[index].count += 1;
+        Some((script, index))
+    }
+    
+    /// Note that the instruction at `offset` of a counted function ran
+    #[inline]
+    pub fn mark(&mut self, (script, function): CoverageSlot, offset: usize) {
+        if let Some(ran) = self.scripts[script].functions[function].executed.get_mut(offset) {
+            *ran = true;
+        }
+    }
+    
+    /// Counters of every script, in run order
+    pub fn scripts(&self) -> &[ScriptCoverage] {
+        &self.scripts
+    }
+}
+
+/// Every function defined in `bytecode`, nested ones included
+fn collect_functions(bytecode: &Bytecode, functions: &mut Vec<FunctionCoverage>) {
+    for constant in &bytecode.constants {
+        if let Constant::Function(function) = constant {
+            let name = function.name.as_deref().unwrap_or("");
+            functions.push(FunctionCoverage::new(name, function.span.start, function.span.end, function.bytecode.len()));
+            collect_functions(&function.bytecode, functions);
+        }
+    }
+}
@@ -9,8 +9,13 @@ use super::parser::Parser;
 use super::compiler::Compiler;
 use super::vm::VirtualMachine;
 use super::value::JsVal;
+use super::heap_snapshot::{AllocationSampler, HeapSnapshot};
+use super::coverage::ScriptCoverage;
+use crate::host::{Callback, HostApi, HostValue};
+use crate::bindgen::DomBindings;
+use fos_dom::{Document, NodeId};
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 /// Custom JavaScript engine implementation.
 ///
@@ -38,24 +43,69 @@ impl CustomEngine {
         }
     }
     
-    /// Convert internal JsVal to external JsValue
-    fn convert_value(val: &JsVal) -> JsValue {
-        use super::value::JsValKind::*;
-        match val.kind() {
-            Undefined => JsValue::Undefined,
-            Null => JsValue::Null,
-            Bool(b) => JsValue::Bool(b),
-            Number(n) => JsValue::Number(n),
-            String(s) => JsValue::String(s.to_string()),
-            Object(_) => JsValue::Object,
-            Array(_) => JsValue::Array,
-            Function(_) => JsValue::Function,
-        }
+    /// Snapshot the JS heap for the DevTools memory panel
+    pub fn heap_snapshot(&self) -> HeapSnapshot {
+        self.vm.lock().unwrap().heap_snapshot()
     }
-}
-
-impl JsEngine for CustomEngine {
-    fn eval(&self, code: &str) -> Result<JsValue, JsError> {
+    
+    /// Start sampling allocations, about one per `interval` bytes
+    pub fn start_allocation_sampling(&self, interval: usize) {
+        self.vm.lock().unwrap().gc_mut().start_sampling(interval);
+    }
+    
+    /// Stop sampling allocations and return the samples
+    pub fn stop_allocation_sampling(&self) -> Option<AllocationSampler> {
+        self.vm.lock().unwrap().gc_mut().stop_sampling()
+    }
+    
+    /// Install an embedder's host functions and objects as globals
+    pub fn install_host_api(&self, api: &HostApi) {
+        api.install(&mut self.vm.lock().unwrap());
+    }
+    
+    /// Define the generated DOM bindings over a document in this engine
+    pub fn install_dom_bindings(&self, document: Arc<Mutex<Document>>) -> DomBindings {
+        DomBindings::install(&mut self.vm.lock().unwrap(), document)
+    }
+    
+    /// Drop the DOM wrappers scripts can no longer reach; returns their nodes
+    pub fn sweep_dom_wrappers(&self, bindings: &DomBindings) -> Vec<NodeId> {
+        bindings.sweep(&self.vm.lock().unwrap())
+    }
+    
+    /// Call a function a script handed to the embedder, such as an event
+    /// listener; an error if it belongs to another engine
+    pub fn call(&self, callback: &Callback, args: &[HostValue]) -> Result<HostValue, JsError> {
+        self.vm.lock().unwrap().call_callback(callback, args).map_err(JsError::Runtime)
+    }
+    
+    /// Realm ID carried by this engine's callbacks
+    pub fn realm(&self) -> u64 {
+        self.vm.lock().unwrap().realm()
+    }
+    
+    /// Run a script loaded from `url`, which names it in coverage reports
+    pub fn exec_script(&self, code: &str, url: &str) -> Result<(), JsError> {
+        self.run(code, url).map(|_| ())
+    }
+    
+    /// Start counting which functions and instructions run, for the
+    /// DevTools coverage panel
+    pub fn start_coverage(&self) {
+        self.vm.lock().unwrap().start_coverage();
+    }
+    
+    /// Coverage of the scripts run since coverage started
+    pub fn take_coverage(&self) -> Vec<ScriptCoverage> {
+        self.vm.lock().unwrap().coverage().map(<[_]>::to_vec).unwrap_or_default()
+    }
+    
+    /// Stop counting and return the coverage
+    pub fn stop_coverage(&self) -> Option<Vec<ScriptCoverage>> {
+        self.vm.lock().unwrap().stop_coverage()
+    }
+    
+    fn run(&self, code: &str, url: &str) -> Result<JsVal, JsError> {
         // Parse
         let parser = Parser::new(code);
         let ast = parser.parse().map_err(|e| JsError::Syntax(e.message))?;
@@ -66,8 +116,18 @@ impl JsEngine for CustomEngine {
         
         // Execute
         let mut vm = self.vm.lock().unwrap();
-        let result = vm.run(&bytecode).map_err(|e| JsError::Runtime(e))?;
-        
+        vm.run_script(&bytecode, url, code.len()).map_err(|e| JsError::Runtime(e))
+    }
+    
+    /// Convert internal JsVal to external JsValue
+    fn convert_value(val: &JsVal) -> JsValue {
+        val.to_host()
+    }
+}
+
+impl JsEngine for CustomEngine {
+    fn eval(&self, code: &str) -> Result<JsValue, JsError> {
+        let result = self.run(code, "")?;
         Ok(Self::convert_value(&result))
     }
     
@@ -86,8 +146,7 @@ impl JsEngine for CustomEngine {
     }
     
     fn memory_usage(&self) -> usize {
-        // TODO: Track actual memory usage
-        0
+        self.vm.lock().unwrap().gc().heap_size()
     }
 }
 
@@ -196,6 +255,54 @@ mod tests {
         assert!(matches!(result, JsValue::Number(n) if (n - 10.0).abs() < 0.001));
     }
     
+    #[test]
+    fn test_methods_and_member_assignment() {
+        let engine = CustomEngine::new();
+        
+        // `this` is the object the method was read from, and function
+        // bodies can read and assign properties
+        engine.exec("function rename(name) { this.name = name; return this.name; }").unwrap();
+        engine.exec("counter = { name: 'a', rename: rename };").unwrap();
+        assert!(matches!(engine.eval("counter.rename('b');").unwrap(), JsValue::String(s) if s == "b"));
+        assert!(matches!(engine.eval("counter.name;").unwrap(), JsValue::String(s) if s == "b"));
+        assert!(matches!(engine.eval("counter === counter;").unwrap(), JsValue::Bool(true)));
+    }
+    
+    #[test]
+    fn test_heap_snapshot() {
+        let engine = CustomEngine::new();
+        engine.start_allocation_sampling(1);
+        engine.eval("let cache = [{}, {}]; cache;").unwrap();
+        
+        let snapshot = engine.heap_snapshot();
+        let names: Vec<&str> = snapshot.nodes().iter().map(|n| &*n.name).collect();
+        assert!(names.contains(&"Array"));
+        assert_eq!(names.iter().filter(|&&n| n == "Object").count(), 2);
+        assert!(engine.memory_usage() > 0);
+        
+        let sampler = engine.stop_allocation_sampling().unwrap();
+        assert_eq!(sampler.samples().len(), 3);
+        assert_eq!(sampler.live_profile(&snapshot)[0].count, 3);
+    }
+    
+    #[test]
+    fn test_coverage() {
+        let engine = CustomEngine::new();
+        engine.start_coverage();
+        let code = "function used() { return 1; } function unused() { return 2; } used(); used();";
+        engine.exec_script(code, "app.js").unwrap();
+        
+        let coverage = engine.stop_coverage().unwrap();
+        assert_eq!(coverage.len(), 1);
+        assert_eq!(coverage[0].url, "app.js");
+        let functions: Vec<(&str, u64)> = coverage[0].functions.iter().map(|f| (&*f.name, f.count)).collect();
+        assert_eq!(functions, vec![("", 1), ("used", 2), ("unused", 0)]);
+        let used = &coverage[0].functions[1];
+        assert_eq!(&code[used.start as usize..used.end as usize], "function used() { return 1; }");
+        assert!(used.executed_instructions() > 0);
+        assert_eq!(coverage[0].functions[2].executed_instructions(), 0);
+    }
+    
     #[test]
     fn test_custom_context() {
         use std::sync::Arc;
@@ -1,13 +1,90 @@
 //! Date Implementation
 //!
-//! JavaScript Date object.
+//! JavaScript Date object. A date is a UTC time value; local time goes
+//! through a [`TimeZone`], the host's unless one is given, so offsets follow
+//! the tz database across DST changes.
 
+use super::tz::{civil_from_days, days_from_civil, days_in_month, weekday_of, TimeZone};
+use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+const MS_PER_DAY: f64 = 86_400_000.0;
+const MS_PER_HOUR: f64 = 3_600_000.0;
+const MS_PER_MINUTE: f64 = 60_000.0;
+
+/// Largest time value either side of the epoch, 100 million days
+const MAX_TIME: f64 = 8.64e15;
+
+const WEEKDAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+/// North American zone names RFC 2822 allows, with their offsets in hours
+const US_ZONES: [(&str, i64); 8] = [
+    ("est", -5), ("edt", -4), ("cst", -6), ("cdt", -5),
+    ("mst", -7), ("mdt", -6), ("pst", -8), ("pdt", -7),
+];
+
+/// Calendar fields of a date in some zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateFields {
+    pub year: i32,
+    /// 0 for January
+    pub month: u32,
+    pub date: u32,
+    /// 0 for Sunday
+    pub day: u32,
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+    pub milliseconds: u32,
+}
+
+impl DateFields {
+    fn of(t: f64) -> Self {
+        let days = (t / MS_PER_DAY).floor();
+        let ms = (t - days * MS_PER_DAY) as u32;
+        let (year, month, date) = civil_from_days(days as i64);
+        Self {
+            year: year as i32,
+            month: month - 1,
+            date,
+            day: weekday_of(days as i64) as u32,
+            hours: ms / 3_600_000,
+            minutes: ms / 60_000 % 60,
+            seconds: ms / 1000 % 60,
+            milliseconds: ms % 1000,
+        }
+    }
+}
+
+/// Time value of calendar fields; months, days and times past their range
+/// carry over, as in `new Date(2024, 13, 40)`
+fn make_time(year: f64, month: f64, date: f64, hours: f64, minutes: f64, seconds: f64, ms: f64) -> f64 {
+    if ![year, month, date, hours, minutes, seconds, ms].iter().all(|v| v.is_finite()) {
+        return f64::NAN;
+    }
+    let year = year + (month / 12.0).floor();
+    // Well past MAX_TIME, and keeps the day arithmetic in range
+    if year.abs() > 400_000.0 {
+        return f64::NAN;
+    }
+    let month = month.rem_euclid(12.0) as u32 + 1;
+    let day = days_from_civil(year as i64, month, 1) as f64 + date.trunc() - 1.0;
+    day * MS_PER_DAY + hours.trunc() * MS_PER_HOUR + minutes.trunc() * MS_PER_MINUTE + seconds.trunc() * 1000.0 + ms.trunc()
+}
+
+/// Whole milliseconds of a time value, or NaN outside the Date range
+fn time_clip(t: f64) -> f64 {
+    if t.is_finite() && t.abs() <= MAX_TIME { t.trunc() + 0.0 } else { f64::NAN }
+}
+
 /// JavaScript Date
 #[derive(Debug, Clone, Copy)]
 pub struct JsDate {
-    /// Milliseconds since Unix epoch
+    /// Milliseconds since Unix epoch, NaN for an invalid date
     timestamp: f64,
 }
 
@@ -22,72 +99,406 @@ impl JsDate {
     }
     
     pub fn from_timestamp(ms: f64) -> Self {
-        Self { timestamp: ms }
+        Self { timestamp: time_clip(ms) }
     }
     
+    /// Date at a local time; `month` is 0-based
     pub fn from_components(year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32, ms: u32) -> Self {
-        // Simplified - just estimate timestamp
-        let days = (year - 1970) as f64 * 365.25 + (month as f64 * 30.44) + day as f64;
-        let secs = days * 86400.0 + hour as f64 * 3600.0 + min as f64 * 60.0 + sec as f64;
-        Self { timestamp: secs * 1000.0 + ms as f64 }
+        Self::from_components_in(TimeZone::host(), year, month, day, hour, min, sec, ms)
+    }
+    
+    /// Date at a wall-clock time in `tz`. A time skipped by a DST change
+    /// lands after it; one repeated is the earlier instant.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_components_in(tz: &TimeZone, year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32, ms: u32) -> Self {
+        let local = make_time(year as f64, month as f64, day as f64, hour as f64, min as f64, sec as f64, ms as f64);
+        Self::from_timestamp(tz.utc_ms(local))
+    }
+    
+    /// Date at a UTC time, as `Date.UTC`
+    pub fn from_utc_components(year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32, ms: u32) -> Self {
+        Self::from_timestamp(make_time(year as f64, month as f64, day as f64, hour as f64, min as f64, sec as f64, ms as f64))
+    }
+    
+    /// Parse a date string as `Date.parse` does, in the host zone
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::parse_in(s, TimeZone::host())
+    }
+    
+    /// Parse ISO 8601 (`2016-11-01T13:23:12+01:00`), RFC 2822
+    /// (`Tue, 01 Nov 2016 13:23:12 +0100`) and the forms `toString` and
+    /// `toUTCString` produce. ISO dates without a time are UTC; other
+    /// strings without an offset are local time in `tz`.
+    pub fn parse_in(s: &str, tz: &TimeZone) -> Option<Self> {
+        let s = s.trim();
+        let (local, offset) = parse_iso(s.as_bytes()).or_else(|| parse_informal(s))?;
+        let utc = match offset {
+            Some(offset) => local - offset,
+            None => tz.utc_ms(local),
+        };
+        Some(Self::from_timestamp(utc)).filter(|date| date.is_valid())
     }
     
     pub fn get_time(&self) -> f64 { self.timestamp }
-    pub fn set_time(&mut self, ms: f64) { self.timestamp = ms; }
+    pub fn set_time(&mut self, ms: f64) { self.timestamp = time_clip(ms); }
+    
+    /// False for a date made from an unparseable string or an out of range
+    /// time; its getters return 0
+    pub fn is_valid(&self) -> bool {
+        !self.timestamp.is_nan()
+    }
+    
+    /// Calendar fields in the host zone
+    pub fn local_fields(&self) -> Option<DateFields> {
+        self.fields_in(TimeZone::host())
+    }
+    
+    /// Calendar fields in `tz`
+    pub fn fields_in(&self, tz: &TimeZone) -> Option<DateFields> {
+        self.is_valid().then(|| DateFields::of(self.timestamp + tz.offset_ms(self.timestamp)))
+    }
+    
+    pub fn utc_fields(&self) -> Option<DateFields> {
+        self.is_valid().then(|| DateFields::of(self.timestamp))
+    }
+    
+    pub fn get_full_year(&self) -> i32 { self.local_fields().map_or(0, |f| f.year) }
+    pub fn get_month(&self) -> u32 { self.local_fields().map_or(0, |f| f.month) }
+    pub fn get_date(&self) -> u32 { self.local_fields().map_or(0, |f| f.date) }
+    pub fn get_day(&self) -> u32 { self.local_fields().map_or(0, |f| f.day) }
+    pub fn get_hours(&self) -> u32 { self.local_fields().map_or(0, |f| f.hours) }
+    pub fn get_minutes(&self) -> u32 { self.local_fields().map_or(0, |f| f.minutes) }
+    pub fn get_seconds(&self) -> u32 { self.local_fields().map_or(0, |f| f.seconds) }
+    pub fn get_milliseconds(&self) -> u32 { self.local_fields().map_or(0, |f| f.milliseconds) }
+    
+    pub fn get_utc_full_year(&self) -> i32 { self.utc_fields().map_or(0, |f| f.year) }
+    pub fn get_utc_month(&self) -> u32 { self.utc_fields().map_or(0, |f| f.month) }
+    pub fn get_utc_date(&self) -> u32 { self.utc_fields().map_or(0, |f| f.date) }
+    pub fn get_utc_day(&self) -> u32 { self.utc_fields().map_or(0, |f| f.day) }
+    pub fn get_utc_hours(&self) -> u32 { self.utc_fields().map_or(0, |f| f.hours) }
+    pub fn get_utc_minutes(&self) -> u32 { self.utc_fields().map_or(0, |f| f.minutes) }
+    pub fn get_utc_seconds(&self) -> u32 { self.utc_fields().map_or(0, |f| f.seconds) }
+    pub fn get_utc_milliseconds(&self) -> u32 { self.utc_fields().map_or(0, |f| f.milliseconds) }
+    
+    /// Minutes UTC is ahead of host local time, as `getTimezoneOffset`
+    pub fn get_timezone_offset(&self) -> f64 {
+        self.get_timezone_offset_in(TimeZone::host())
+    }
+    
+    pub fn get_timezone_offset_in(&self, tz: &TimeZone) -> f64 {
+        if !self.is_valid() {
+            return f64::NAN;
+        }
+        -tz.offset_ms(self.timestamp) / MS_PER_MINUTE + 0.0
+    }
+    
+    /// `2016-11-01T12:23:12.000Z`; a RangeError for an invalid date
+    pub fn to_iso_string(&self) -> Result<String, String> {
+        let f = self.utc_fields().ok_or("RangeError: Invalid time value")?;
+        let year = match f.year {
+            0..=9999 => format!("{:04}", f.year),
+            _ => format!("{}{:06}", if f.year < 0 { '-' } else { '+' }, f.year.unsigned_abs()),
+        };
+        Ok(format!("{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year, f.month + 1, f.date, f.hours, f.minutes, f.seconds, f.milliseconds))
+    }
     
-    // Simplified date component extraction
-    pub fn get_full_year(&self) -> i32 {
-        1970 + (self.timestamp / (365.25 * 86400.0 * 1000.0)) as i32
+    /// `Tue Nov 01 2016 13:23:12 GMT+0100 (CET)` in `tz`
+    pub fn to_string_in(&self, tz: &TimeZone) -> String {
+        let Some(f) = self.fields_in(tz) else { return "Invalid Date".into() };
+        let offset = tz.local_type((self.timestamp / 1000.0).floor() as i64);
+        let minutes = offset.offset.abs() / 60;
+        format!("{} {} {:02} {} {:02}:{:02}:{:02} GMT{}{:02}{:02} ({})",
+            &WEEKDAYS[f.day as usize][..3], &MONTHS[f.month as usize][..3], f.date, year_string(f.year),
+            f.hours, f.minutes, f.seconds,
+            if offset.offset < 0 { '-' } else { '+' }, minutes / 60, minutes % 60, offset.abbreviation)
     }
     
-    pub fn get_month(&self) -> u32 {
-        let days_since_epoch = self.timestamp / (86400.0 * 1000.0);
-        let year_days = days_since_epoch % 365.25;
-        (year_days / 30.44) as u32
+    /// `Tue, 01 Nov 2016 12:23:12 GMT`
+    pub fn to_utc_string(&self) -> String {
+        let Some(f) = self.utc_fields() else { return "Invalid Date".into() };
+        format!("{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            &WEEKDAYS[f.day as usize][..3], f.date, &MONTHS[f.month as usize][..3], year_string(f.year),
+            f.hours, f.minutes, f.seconds)
     }
     
-    pub fn get_date(&self) -> u32 {
-        let days_since_epoch = self.timestamp / (86400.0 * 1000.0);
-        let year_days = days_since_epoch % 365.25;
-        (year_days % 30.44) as u32 + 1
+    /// en-US form in the host zone, `11/1/2016, 1:23:12 PM`
+    pub fn to_locale_string(&self) -> String {
+        self.to_locale_string_in(TimeZone::host())
     }
     
-    pub fn get_day(&self) -> u32 {
-        let days_since_epoch = self.timestamp / (86400.0 * 1000.0);
-        ((days_since_epoch as i64 + 4) % 7) as u32 // Jan 1, 1970 was Thursday
+    pub fn to_locale_string_in(&self, tz: &TimeZone) -> String {
+        let Some(f) = self.fields_in(tz) else { return "Invalid Date".into() };
+        let hour = match f.hours % 12 { 0 => 12, h => h };
+        format!("{}/{}/{}, {}:{:02}:{:02} {}",
+            f.month + 1, f.date, f.year, hour, f.minutes, f.seconds, if f.hours < 12 { "AM" } else { "PM" })
+    }
+}
+
+impl fmt::Display for JsDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string_in(TimeZone::host()))
+    }
+}
+
+/// Years in `toString` and `toUTCString`: at least four digits
+fn year_string(year: i32) -> String {
+    format!("{}{:04}", if year < 0 { "-" } else { "" }, year.unsigned_abs())
+}
+
+/// Cursor over a date string
+struct Cursor<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl Cursor<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
     }
     
-    pub fn get_hours(&self) -> u32 {
-        ((self.timestamp / (3600.0 * 1000.0)) % 24.0) as u32
+    fn eat(&mut self, c: u8) -> bool {
+        let found = self.peek() == Some(c);
+        self.pos += found as usize;
+        found
     }
     
-    pub fn get_minutes(&self) -> u32 {
-        ((self.timestamp / (60.0 * 1000.0)) % 60.0) as u32
+    /// Exactly `n` digits
+    fn digits(&mut self, n: usize) -> Option<i64> {
+        let digits = self.s.get(self.pos..self.pos + n)?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        self.pos += n;
+        Some(digits.iter().fold(0, |acc, &d| acc * 10 + (d - b'0') as i64))
     }
     
-    pub fn get_seconds(&self) -> u32 {
-        ((self.timestamp / 1000.0) % 60.0) as u32
+    /// A run of digits and how many there were
+    fn number(&mut self) -> Option<(i64, usize)> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits = &self.s[start..self.pos];
+        if digits.is_empty() || digits.len() > 9 {
+            return None;
+        }
+        Some((digits.iter().fold(0, |acc, &d| acc * 10 + (d - b'0') as i64), digits.len()))
     }
     
-    pub fn get_milliseconds(&self) -> u32 {
-        (self.timestamp % 1000.0) as u32
+    /// Milliseconds of a fraction of a second, ignoring digits past three
+    fn fraction(&mut self) -> Option<i64> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits = &self.s[start..self.pos];
+        if digits.is_empty() {
+            return None;
+        }
+        Some((0..3).fold(0, |acc, i| acc * 10 + digits.get(i).map_or(0, |&d| (d - b'0') as i64)))
+    }
+}
+
+fn valid_time(hours: i64, minutes: i64, seconds: i64, ms: i64) -> bool {
+    (hours < 24 || (hours == 24 && minutes == 0 && seconds == 0 && ms == 0)) && minutes < 60 && seconds < 60
+}
+
+fn valid_date(year: i64, month: i64, day: i64) -> bool {
+    (1..=12).contains(&month) && day >= 1 && day <= days_in_month(year, month as u32)
+}
+
+/// ECMAScript's ISO 8601 subset: the time value of the fields and the
+/// offset in milliseconds, None for local time
+fn parse_iso(s: &[u8]) -> Option<(f64, Option<f64>)> {
+    let mut p = Cursor { s, pos: 0 };
+    let year = match p.peek()? {
+        sign @ (b'+' | b'-') => {
+            p.pos += 1;
+            let year = p.digits(6)?;
+            // -000000 is not a year
+            if sign == b'-' && year == 0 {
+                return None;
+            }
+            if sign == b'-' { -year } else { year }
+        }
+        _ => p.digits(4)?,
+    };
+    let (mut month, mut day) = (1, 1);
+    if p.eat(b'-') {
+        month = p.digits(2)?;
+        if p.eat(b'-') {
+            day = p.digits(2)?;
+        }
     }
     
-    pub fn to_iso_string(&self) -> String {
-        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
-            self.get_full_year(), self.get_month() + 1, self.get_date(),
-            self.get_hours(), self.get_minutes(), self.get_seconds(), self.get_milliseconds())
+    let (mut hours, mut minutes, mut seconds, mut ms) = (0, 0, 0, 0);
+    let mut offset = Some(0.0);
+    if p.eat(b'T') || p.eat(b't') || p.eat(b' ') {
+        hours = p.digits(2)?;
+        if !p.eat(b':') {
+            return None;
+        }
+        minutes = p.digits(2)?;
+        if p.eat(b':') {
+            seconds = p.digits(2)?;
+            if p.eat(b'.') || p.eat(b',') {
+                ms = p.fraction()?;
+            }
+        }
+        offset = match p.peek() {
+            Some(b'Z' | b'z') => { p.pos += 1; Some(0.0) }
+            Some(sign @ (b'+' | b'-')) => {
+                p.pos += 1;
+                let h = p.digits(2)?;
+                p.eat(b':');
+                let m = p.digits(2)?;
+                if h > 23 || m > 59 {
+                    return None;
+                }
+                let offset = (h * 60 + m) as f64 * MS_PER_MINUTE;
+                Some(if sign == b'-' { -offset } else { offset })
+            }
+            _ => None,
+        };
     }
     
-    pub fn to_string(&self) -> String {
-        self.to_iso_string()
+    if p.pos != s.len() || !valid_date(year, month, day) || !valid_time(hours, minutes, seconds, ms) {
+        return None;
+    }
+    Some((make_time(year as f64, (month - 1) as f64, day as f64, hours as f64, minutes as f64, seconds as f64, ms as f64), offset))
+}
+
+/// `s` without parenthesized comments, which may nest
+fn strip_comments(s: &str) -> Option<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut depth = 0usize;
+    for c in s.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1)?,
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
     }
+    (depth == 0).then_some(out)
+}
+
+/// Index of the name `word` abbreviates, at least three letters of it
+fn name_index(names: &[&str], word: &str) -> Option<usize> {
+    if word.len() < 3 {
+        return None;
+    }
+    names.iter().position(|name| name.len() >= word.len() && name[..word.len()].eq_ignore_ascii_case(word))
+}
+
+/// RFC 2822 and the looser forms browsers accept, like `Nov 1, 2016`,
+/// `1 November 2016 1:23 PM` and `11/1/2016`
+fn parse_informal(s: &str) -> Option<(f64, Option<f64>)> {
+    let s = strip_comments(s)?;
+    let mut p = Cursor { s: s.as_bytes(), pos: 0 };
+    let mut month = None;
+    let mut numbers: Vec<(i64, usize)> = Vec::new();
+    let mut slashed = None;
+    let mut time = None;
+    let mut offset = None;
+    let mut pm = None;
+    
+    while let Some(c) = p.peek() {
+        if c.is_ascii_alphabetic() {
+            let start = p.pos;
+            while p.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                p.pos += 1;
+            }
+            let word = s[start..p.pos].to_ascii_lowercase();
+            if let Some(m) = name_index(&MONTHS, &word) {
+                month = Some(m as i64 + 1);
+            } else if name_index(&WEEKDAYS, &word).is_some() {
+                // Weekdays are not checked against the date
+            } else if word == "am" || word == "pm" {
+                pm = Some(word == "pm");
+            } else if matches!(word.as_str(), "gmt" | "ut" | "utc" | "z") {
+                offset = Some(0.0);
+            } else if let Some(&(_, hours)) = US_ZONES.iter().find(|(name, _)| *name == word) {
+                offset = Some(hours as f64 * MS_PER_HOUR);
+            } else {
+                return None;
+            }
+        } else if c.is_ascii_digit() {
+            let (n, len) = p.number()?;
+            if p.eat(b':') {
+                let minutes = p.digits(2)?;
+                let (mut seconds, mut ms) = (0, 0);
+                if p.eat(b':') {
+                    seconds = p.digits(2)?;
+                    if p.eat(b'.') {
+                        ms = p.fraction()?;
+                    }
+                }
+                time = Some((n, minutes, seconds, ms));
+            } else if p.eat(b'/') {
+                let (day, _) = p.number()?;
+                if !p.eat(b'/') {
+                    return None;
+                }
+                slashed = Some((n, day, p.number()?));
+            } else {
+                numbers.push((n, len));
+            }
+        } else if (c == b'+' || c == b'-') && (time.is_some() || offset.is_some()) {
+            // `+0100`, `+01:00` or `+1`, after the time or `GMT`
+            p.pos += 1;
+            let (n, len) = p.number()?;
+            let minutes = if len <= 2 {
+                let m = if p.eat(b':') { p.digits(2)? } else { 0 };
+                n * 60 + m
+            } else {
+                n / 100 * 60 + n % 100
+            };
+            let minutes = if c == b'-' { -minutes } else { minutes };
+            offset = Some(minutes as f64 * MS_PER_MINUTE);
+        } else if matches!(c, b' ' | b'\t' | b',' | b'.' | b'-') {
+            p.pos += 1;
+        } else {
+            return None;
+        }
+    }
+    
+    let (month, day, (year, year_len)) = match (slashed, month, numbers.as_slice()) {
+        (Some((month, day, year)), None, []) => (month, day, year),
+        (None, Some(month), &[a, b]) => {
+            // `1 Nov 2016`, `Nov 1 2016` or `2016 Nov 1`
+            if a.1 >= 3 || a.0 > 31 { (month, b.0, a) } else { (month, a.0, b) }
+        }
+        _ => return None,
+    };
+    let year = match (year, year_len) {
+        (y @ 0..=49, 1..=2) => y + 2000,
+        (y, 1..=2) => y + 1900,
+        (y, _) => y,
+    };
+    
+    let (mut hours, minutes, seconds, ms) = time.unwrap_or((0, 0, 0, 0));
+    if let Some(pm) = pm {
+        if !(1..=12).contains(&hours) || time.is_none() {
+            return None;
+        }
+        hours = hours % 12 + if pm { 12 } else { 0 };
+    }
+    if !valid_date(year, month, day) || !valid_time(hours, minutes, seconds, ms) {
+        return None;
+    }
+    Some((make_time(year as f64, (month - 1) as f64, day as f64, hours as f64, minutes as f64, seconds as f64, ms as f64), offset))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     
+    fn new_york() -> TimeZone {
+        TimeZone::posix("EST5EDT,M3.2.0,M11.1.0").unwrap()
+    }
+    
     #[test]
     fn test_date_now() {
         let d = JsDate::now();
@@ -97,12 +508,68 @@ mod tests {
     #[test]
     fn test_date_from_timestamp() {
         let d = JsDate::from_timestamp(0.0);
-        assert_eq!(d.get_full_year(), 1970);
+        assert_eq!(d.get_utc_full_year(), 1970);
+        let local = d.fields_in(&new_york()).unwrap();
+        assert_eq!((local.year, local.month, local.date, local.hours), (1969, 11, 31, 19));
+        assert_eq!(d.get_timezone_offset_in(&new_york()), 300.0);
+        
+        assert!(!JsDate::from_timestamp(8.64e15 + 1.0).is_valid());
+        assert_eq!(JsDate::from_timestamp(f64::NAN).to_string_in(&new_york()), "Invalid Date");
     }
     
     #[test]
     fn test_date_components() {
-        let d = JsDate::from_timestamp(1000.0 * 3600.0 * 24.0 * 365.0);
-        assert!(d.get_full_year() >= 1970);
+        let tz = new_york();
+        let d = JsDate::from_components_in(&tz, 2024, 6, 4, 12, 0, 0, 0);
+        assert_eq!(d.to_iso_string().unwrap(), "2024-07-04T16:00:00.000Z");
+        assert_eq!(d.get_timezone_offset_in(&tz), 240.0);
+        
+        // 02:30 on 2024-03-10 is skipped and lands at 03:30 EDT
+        let d = JsDate::from_components_in(&tz, 2024, 2, 10, 2, 30, 0, 0);
+        assert_eq!(d.to_iso_string().unwrap(), "2024-03-10T07:30:00.000Z");
+        // 01:30 on 2024-11-03 happens twice; the first is EDT
+        let d = JsDate::from_components_in(&tz, 2024, 10, 3, 1, 30, 0, 0);
+        assert_eq!(d.to_iso_string().unwrap(), "2024-11-03T05:30:00.000Z");
+        
+        // Months and days carry over
+        let d = JsDate::from_utc_components(2023, 13, 29, 0, 0, 0, 0);
+        assert_eq!(d.to_iso_string().unwrap(), "2024-02-29T00:00:00.000Z");
+        assert_eq!(d.get_utc_day(), 4);
+    }
+    
+    #[test]
+    fn test_date_parse() {
+        let tz = new_york();
+        let parse = |s: &str| JsDate::parse_in(s, &tz).map(|d| d.to_iso_string().unwrap());
+        
+        assert_eq!(parse("2016-11-01").as_deref(), Some("2016-11-01T00:00:00.000Z"));
+        assert_eq!(parse("2016-11-01T13:23:12").as_deref(), Some("2016-11-01T17:23:12.000Z"));
+        assert_eq!(parse("2016-11-01T13:23:12.5+01:00").as_deref(), Some("2016-11-01T12:23:12.500Z"));
+        assert_eq!(parse("2016-11-01 13:23Z").as_deref(), Some("2016-11-01T13:23:00.000Z"));
+        assert_eq!(parse("+275760-09-13T00:00:00Z").as_deref(), Some("+275760-09-13T00:00:00.000Z"));
+        assert_eq!(parse("2016-02-30"), None);
+        
+        assert_eq!(parse("Tue, 01 Nov 2016 13:23:12 +0100").as_deref(), Some("2016-11-01T12:23:12.000Z"));
+        assert_eq!(parse("Tue, 1 Nov 16 13:23:12 GMT").as_deref(), Some("2016-11-01T13:23:12.000Z"));
+        assert_eq!(parse("Tue Nov 01 2016 13:23:12 GMT+0100 (Central European Time)").as_deref(), Some("2016-11-01T12:23:12.000Z"));
+        assert_eq!(parse("1 Nov 2016 08:00 PST").as_deref(), Some("2016-11-01T16:00:00.000Z"));
+        assert_eq!(parse("November 1, 2016 1:23 PM").as_deref(), Some("2016-11-01T17:23:00.000Z"));
+        assert_eq!(parse("7/4/2024").as_deref(), Some("2024-07-04T04:00:00.000Z"));
+        assert_eq!(parse("not a date"), None);
+    }
+    
+    #[test]
+    fn test_date_format() {
+        let tz = new_york();
+        let d = JsDate::parse_in("2016-11-01T12:23:12Z", &tz).unwrap();
+        assert_eq!(d.to_string_in(&tz), "Tue Nov 01 2016 08:23:12 GMT-0400 (EDT)");
+        assert_eq!(d.to_utc_string(), "Tue, 01 Nov 2016 12:23:12 GMT");
+        assert_eq!(d.to_locale_string_in(&tz), "11/1/2016, 8:23:12 AM");
+        assert_eq!(JsDate::parse_in(&d.to_string_in(&tz), &tz).unwrap().get_time(), d.get_time());
+        
+        let winter = JsDate::parse_in("2016-12-01T18:00:00Z", &tz).unwrap();
+        assert_eq!(winter.to_locale_string_in(&tz), "12/1/2016, 1:00:00 PM");
+        assert!(winter.to_string_in(&tz).ends_with("GMT-0500 (EST)"));
+        assert!(JsDate::from_timestamp(f64::NAN).to_iso_string().is_err());
     }
 }
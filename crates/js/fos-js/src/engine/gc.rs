@@ -1,9 +1,11 @@
 //! Garbage Collector
 //!
-//! Mark-and-sweep garbage collector for JavaScript objects.
+//! Mark-and-sweep garbage collector for JavaScript objects, with heap
+//! snapshots and allocation sampling for the DevTools memory panel.
 
 use super::value::JsVal;
 use super::object::{JsObject, JsArray};
+use super::heap_snapshot::{AllocationSampler, EdgeName, HeapCells, HeapNodeId, HeapSnapshot};
 use std::collections::HashSet;
 
 /// GC statistics
@@ -19,6 +21,7 @@ pub struct GarbageCollector {
     heap_size: usize,
     threshold: usize,
     stats: GcStats,
+    sampler: Option<AllocationSampler>,
 }
 
 impl Default for GarbageCollector {
@@ -27,11 +30,11 @@ impl Default for GarbageCollector {
 
 impl GarbageCollector {
     pub fn new() -> Self {
-        Self { heap_size: 0, threshold: 1024 * 1024, stats: GcStats::default() }
+        Self { heap_size: 0, threshold: 1024 * 1024, stats: GcStats::default(), sampler: None }
     }
     
     pub fn with_threshold(threshold: usize) -> Self {
-        Self { heap_size: 0, threshold, stats: GcStats::default() }
+        Self { heap_size: 0, threshold, stats: GcStats::default(), sampler: None }
     }
     
     /// Check if collection is needed
@@ -70,6 +73,29 @@ impl GarbageCollector {
     /// Record allocation
     pub fn record_alloc(&mut self, bytes: usize) { self.heap_size += bytes; }
     
+    /// Record allocation of a heap cell by the function calls in `stack`
+    pub fn record_object_alloc(&mut self, node: HeapNodeId, bytes: usize, stack: &[Box<str>]) {
+        self.record_alloc(bytes);
+        if let Some(sampler) = &mut self.sampler {
+            sampler.record(node, bytes, stack);
+        }
+    }
+    
+    /// Start sampling about one allocation per `interval` bytes
+    pub fn start_sampling(&mut self, interval: usize) {
+        self.sampler = Some(AllocationSampler::new(interval));
+    }
+    
+    /// Stop sampling and hand back the samples
+    pub fn stop_sampling(&mut self) -> Option<AllocationSampler> { self.sampler.take() }
+    
+    pub fn sampler(&self) -> Option<&AllocationSampler> { self.sampler.as_ref() }
+    
+    /// Snapshot the object graph reachable from `roots`
+    pub fn snapshot(&self, roots: &[(EdgeName, JsVal)], cells: HeapCells<'_>) -> HeapSnapshot {
+        HeapSnapshot::capture(roots, cells)
+    }
+    
     /// Record freed memory
     pub fn record_free(&mut self, bytes: usize, count: u64) {
         self.heap_size = self.heap_size.saturating_sub(bytes);
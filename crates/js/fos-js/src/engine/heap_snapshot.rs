@@ -0,0 +1,518 @@
+//! Heap Snapshots
+//!
+//! Walks the object graph from the VM roots and records every reachable
+//! object, array and closure with its size, constructor name and retainers.
+//! Retained sizes come from the dominator tree, two snapshots can be diffed
+//! per constructor to find what keeps growing, and the allocation sampler
+//! attributes sampled allocations to the call stacks that made them.
+
+use super::value::JsVal;
+use super::object::{JsObject, JsArray};
+use super::vm::{Closure, Upvalue};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+/// Kind of heap cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HeapNodeKind {
+    Object,
+    Array,
+    Closure,
+}
+
+/// Identity of a heap cell
+///
+/// Cells are never moved, so the same object has the same id in every
+/// snapshot taken from one VM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HeapNodeId {
+    pub kind: HeapNodeKind,
+    pub index: u32,
+}
+
+impl HeapNodeId {
+    pub fn object(index: u32) -> Self { Self { kind: HeapNodeKind::Object, index } }
+    pub fn array(index: u32) -> Self { Self { kind: HeapNodeKind::Array, index } }
+    pub fn closure(index: u32) -> Self { Self { kind: HeapNodeKind::Closure, index } }
+    
+    /// Heap cell a value points at, if any
+    pub fn of(val: &JsVal) -> Option<Self> {
+        val.as_object_id().map(Self::object)
+            .or_else(|| val.as_array_id().map(Self::array))
+            .or_else(|| val.as_function_id().map(Self::closure))
+    }
+}
+
+/// Reachable heap cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeapNode {
+    pub id: HeapNodeId,
+    /// Constructor name, `Array`, or the function name of a closure
+    pub name: Box<str>,
+    /// Bytes of the cell itself
+    pub self_size: usize,
+    /// Bytes freed if this cell were collected
+    pub retained_size: usize,
+}
+
+/// How a retainer refers to a cell
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EdgeName {
+    Property(Box<str>),
+    Element(u32),
+    Prototype,
+    /// Captured variable of a closure
+    Upvalue(u32),
+    /// Root: global variable
+    Global(Box<str>),
+    /// Root: operand stack slot
+    Stack(u32),
+    /// Root: current `this`
+    This,
+}
+
+/// Reference between cells; `from` is `None` for GC roots
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeapEdge {
+    pub from: Option<HeapNodeId>,
+    pub to: HeapNodeId,
+    pub name: EdgeName,
+}
+
+/// Per-constructor change between two snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDelta {
+    pub name: Box<str>,
+    pub added: usize,
+    pub removed: usize,
+    pub size_delta: i64,
+}
+
+impl ClassDelta {
+    pub fn count_delta(&self) -> i64 { self.added as i64 - self.removed as i64 }
+}
+
+/// Heap cells of a VM, borrowed for a snapshot
+#[derive(Clone, Copy)]
+pub struct HeapCells<'a> {
+    pub objects: &'a [JsObject],
+    pub arrays: &'a [JsArray],
+    pub closures: &'a [Arc<Closure>],
+}
+
+impl HeapCells<'_> {
+    /// Outgoing references of a cell
+    fn children(&self, id: HeapNodeId) -> Vec<(EdgeName, HeapNodeId)> {
+        let mut out = Vec::new();
+        match id.kind {
+            HeapNodeKind::Object => if let Some(obj) = self.objects.get(id.index as usize) {
+                for key in obj.keys() {
+                    if let Some(to) = obj.get(key).and_then(HeapNodeId::of) {
+                        out.push((EdgeName::Property(key.into()), to));
+                    }
+                }
+                if let Some(proto) = obj.prototype() {
+                    out.push((EdgeName::Prototype, HeapNodeId::object(proto)));
+                }
+            },
+            HeapNodeKind::Array => if let Some(arr) = self.arrays.get(id.index as usize) {
+                for i in 0..arr.len() {
+                    if let Some(to) = HeapNodeId::of(&arr.get(i)) {
+                        out.push((EdgeName::Element(i as u32), to));
+                    }
+                }
+            },
+            HeapNodeKind::Closure => if let Some(closure) = self.closures.get(id.index as usize) {
+                for (i, upvalue) in closure.upvalues.iter().enumerate() {
+                    // Open upvalues point into the stack, which is a root already
+                    let captured = match upvalue.lock().as_deref() {
+                        Ok(Upvalue::Closed { value }) => HeapNodeId::of(value),
+                        _ => None,
+                    };
+                    if let Some(to) = captured {
+                        out.push((EdgeName::Upvalue(i as u32), to));
+                    }
+                }
+            },
+        }
+        out
+    }
+    
+    fn exists(&self, id: HeapNodeId) -> bool {
+        let len = match id.kind {
+            HeapNodeKind::Object => self.objects.len(),
+            HeapNodeKind::Array => self.arrays.len(),
+            HeapNodeKind::Closure => self.closures.len(),
+        };
+        (id.index as usize) < len
+    }
+    
+    fn name(&self, id: HeapNodeId) -> Box<str> {
+        match id.kind {
+            HeapNodeKind::Object => self.objects.get(id.index as usize)
+                .and_then(JsObject::prototype)
+                .and_then(|proto| self.objects.get(proto as usize))
+                .and_then(|proto| proto.get("constructor"))
+                .and_then(JsVal::as_function_id)
+                .and_then(|f| self.closure_name(f))
+                .unwrap_or_else(|| "Object".into()),
+            HeapNodeKind::Array => "Array".into(),
+            HeapNodeKind::Closure => self.closure_name(id.index).unwrap_or_else(|| "(anonymous)".into()),
+        }
+    }
+    
+    fn closure_name(&self, index: u32) -> Option<Box<str>> {
+        self.closures.get(index as usize)?.function.name.clone()
+    }
+    
+    fn size(&self, id: HeapNodeId) -> usize {
+        match id.kind {
+            HeapNodeKind::Object => self.objects.get(id.index as usize).map_or(0, object_size),
+            HeapNodeKind::Array => self.arrays.get(id.index as usize).map_or(0, array_size),
+            HeapNodeKind::Closure => self.closures.get(id.index as usize).map_or(0, |c| closure_size(c)),
+        }
+    }
+}
+
+/// Cells reachable from `roots`
+pub fn reachable(roots: &[(EdgeName, JsVal)], cells: HeapCells<'_>) -> HashSet<HeapNodeId> {
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<HeapNodeId> = roots.iter()
+        .filter_map(|(_, val)| HeapNodeId::of(val))
+        .collect();
+    while let Some(id) = queue.pop_front() {
+        if !cells.exists(id) || !seen.insert(id) { continue; }
+        queue.extend(cells.children(id).into_iter().map(|(_, to)| to));
+    }
+    seen
+}
+
+/// Approximate bytes of an object, counting spilled properties and keys
+pub fn object_size(obj: &JsObject) -> usize {
+    let keys: usize = obj.keys().map(str::len).sum();
+    let spilled = obj.keys().count().saturating_sub(4);
+    std::mem::size_of::<JsObject>() + spilled * (std::mem::size_of::<Box<str>>() + std::mem::size_of::<JsVal>()) + keys
+}
+
+/// Approximate bytes of an array and its elements
+pub fn array_size(arr: &JsArray) -> usize {
+    std::mem::size_of::<JsArray>() + arr.len() * std::mem::size_of::<JsVal>()
+}
+
+/// Approximate bytes of a closure and its captured variables
+pub fn closure_size(closure: &Closure) -> usize {
+    std::mem::size_of::<Closure>() + closure.upvalues.len() * std::mem::size_of::<Upvalue>()
+}
+
+/// Heap snapshot
+#[derive(Debug, Clone, Default)]
+pub struct HeapSnapshot {
+    nodes: Vec<HeapNode>,
+    edges: Vec<HeapEdge>,
+    index: HashMap<HeapNodeId, usize>,
+}
+
+impl HeapSnapshot {
+    /// Record every cell reachable from `roots`
+    pub fn capture(roots: &[(EdgeName, JsVal)], cells: HeapCells<'_>) -> Self {
+        let mut snapshot = Self::default();
+        let mut queue = VecDeque::new();
+        
+        for (name, val) in roots {
+            if let Some(to) = HeapNodeId::of(val).filter(|&id| cells.exists(id)) {
+                snapshot.edges.push(HeapEdge { from: None, to, name: name.clone() });
+                if snapshot.visit(to, &cells) { queue.push_back(to); }
+            }
+        }
+        
+        while let Some(from) = queue.pop_front() {
+            for (name, to) in cells.children(from) {
+                if !cells.exists(to) { continue; }
+                snapshot.edges.push(HeapEdge { from: Some(from), to, name });
+                if snapshot.visit(to, &cells) { queue.push_back(to); }
+            }
+        }
+        
+        snapshot.compute_retained_sizes();
+        snapshot
+    }
+    
+    /// Add a node the first time it is reached
+    fn visit(&mut self, id: HeapNodeId, cells: &HeapCells<'_>) -> bool {
+        if self.index.contains_key(&id) { return false; }
+        self.index.insert(id, self.nodes.len());
+        let self_size = cells.size(id);
+        self.nodes.push(HeapNode { id, name: cells.name(id), self_size, retained_size: self_size });
+        true
+    }
+    
+    /// Retained sizes from the dominator tree (Cooper, Harvey and Kennedy)
+    fn compute_retained_sizes(&mut self) {
+        // Slot 0 is the synthetic root above all GC roots
+        let count = self.nodes.len() + 1;
+        let slot = |id: Option<HeapNodeId>, index: &HashMap<HeapNodeId, usize>| id.map_or(0, |id| index[&id] + 1);
+        let mut succs = vec![Vec::new(); count];
+        let mut preds = vec![Vec::new(); count];
+        for edge in &self.edges {
+            let (from, to) = (slot(edge.from, &self.index), slot(Some(edge.to), &self.index));
+            succs[from].push(to);
+            preds[to].push(from);
+        }
+        
+        // Depth-first postorder from the root
+        let mut postorder = Vec::with_capacity(count);
+        let mut order = vec![usize::MAX; count];
+        let mut seen = vec![false; count];
+        let mut stack = vec![(0, 0)];
+        seen[0] = true;
+        while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+            if let Some(&succ) = succs[node].get(*next) {
+                *next += 1;
+                if !seen[succ] {
+                    seen[succ] = true;
+                    stack.push((succ, 0));
+                }
+            } else {
+                order[node] = postorder.len();
+                postorder.push(node);
+                stack.pop();
+            }
+        }
+        
+        let mut idom = vec![usize::MAX; count];
+        idom[0] = 0;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in postorder.iter().rev().skip(1) {
+                let mut new_idom = usize::MAX;
+                for &pred in &preds[node] {
+                    if idom[pred] == usize::MAX { continue; }
+                    new_idom = if new_idom == usize::MAX {
+                        pred
+                    } else {
+                        let (mut a, mut b) = (pred, new_idom);
+                        while a != b {
+                            while order[a] < order[b] { a = idom[a]; }
+                            while order[b] < order[a] { b = idom[b]; }
+                        }
+                        a
+                    };
+                }
+                if idom[node] != new_idom {
+                    idom[node] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        
+        // A dominator always finishes after the nodes it dominates
+        let mut retained: Vec<usize> = std::iter::once(0).chain(self.nodes.iter().map(|n| n.self_size)).collect();
+        for &node in &postorder {
+            if node != 0 && idom[node] != usize::MAX {
+                retained[idom[node]] += retained[node];
+            }
+        }
+        for (i, node) in self.nodes.iter_mut().enumerate() {
+            node.retained_size = retained[i + 1];
+        }
+    }
+    
+    /// Reachable cells in breadth-first order from the roots
+    pub fn nodes(&self) -> &[HeapNode] { &self.nodes }
+    
+    pub fn edges(&self) -> &[HeapEdge] { &self.edges }
+    
+    pub fn node(&self, id: HeapNodeId) -> Option<&HeapNode> {
+        self.index.get(&id).map(|&i| &self.nodes[i])
+    }
+    
+    /// Bytes of all reachable cells
+    pub fn total_size(&self) -> usize {
+        self.nodes.iter().map(|n| n.self_size).sum()
+    }
+    
+    /// Edges that keep a cell alive
+    pub fn retainers(&self, id: HeapNodeId) -> impl Iterator<Item = &HeapEdge> {
+        self.edges.iter().filter(move |e| e.to == id)
+    }
+    
+    /// Shortest chain of references from a GC root to a cell
+    pub fn retaining_path(&self, id: HeapNodeId) -> Vec<&HeapEdge> {
+        let mut path = Vec::new();
+        let mut current = id;
+        let mut visited = HashSet::new();
+        // Breadth-first discovery makes the first retainer the one closest to a root
+        while let Some(edge) = self.edges.iter().find(|e| e.to == current) {
+            if !visited.insert(current) { break; }
+            path.push(edge);
+            match edge.from {
+                Some(from) => current = from,
+                None => break,
+            }
+        }
+        path.reverse();
+        path
+    }
+    
+    /// Growth per constructor from `before` to this snapshot, largest first
+    pub fn diff(&self, before: &HeapSnapshot) -> Vec<ClassDelta> {
+        let mut classes: HashMap<&str, ClassDelta> = HashMap::new();
+        let added = self.nodes.iter().filter(|n| before.node(n.id).is_none()).map(|n| (n, true));
+        let removed = before.nodes.iter().filter(|n| self.node(n.id).is_none()).map(|n| (n, false));
+        
+        for (node, is_added) in added.chain(removed) {
+            let delta = classes.entry(&node.name)
+                .or_insert_with(|| ClassDelta { name: node.name.clone(), added: 0, removed: 0, size_delta: 0 });
+            if is_added {
+                delta.added += 1;
+                delta.size_delta += node.self_size as i64;
+            } else {
+                delta.removed += 1;
+                delta.size_delta -= node.self_size as i64;
+            }
+        }
+        
+        let mut deltas: Vec<ClassDelta> = classes.into_values().collect();
+        deltas.sort_by(|a, b| b.size_delta.cmp(&a.size_delta).then_with(|| a.name.cmp(&b.name)));
+        deltas
+    }
+}
+
+/// Sampled allocation
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocationSample {
+    pub node: HeapNodeId,
+    pub size: usize,
+    /// Function names, outermost first
+    pub stack: Vec<Box<str>>,
+}
+
+/// Allocation bytes attributed to one call stack
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocationSite {
+    pub stack: Vec<Box<str>>,
+    pub count: usize,
+    /// Estimated bytes, each sample standing for one sampling interval
+    pub bytes: usize,
+}
+
+/// Samples about one allocation per `interval` bytes allocated
+#[derive(Debug, Clone)]
+pub struct AllocationSampler {
+    interval: usize,
+    remaining: usize,
+    samples: Vec<AllocationSample>,
+}
+
+impl AllocationSampler {
+    /// Default interval, matching the 32 KiB of the Chrome sampling profiler
+    pub const DEFAULT_INTERVAL: usize = 32 * 1024;
+    
+    pub fn new(interval: usize) -> Self {
+        let interval = interval.max(1);
+        Self { interval, remaining: interval, samples: Vec::new() }
+    }
+    
+    pub fn interval(&self) -> usize { self.interval }
+    
+    /// Count an allocation, sampling it when the interval runs out
+    pub fn record(&mut self, node: HeapNodeId, size: usize, stack: &[Box<str>]) {
+        if size < self.remaining {
+            self.remaining -= size;
+            return;
+        }
+        self.remaining = self.interval;
+        self.samples.push(AllocationSample { node, size, stack: stack.to_vec() });
+    }
+    
+    pub fn samples(&self) -> &[AllocationSample] { &self.samples }
+    
+    /// Sampled allocations per call stack, heaviest first
+    pub fn profile(&self) -> Vec<AllocationSite> {
+        self.aggregate(|_| true)
+    }
+    
+    /// Like [`profile`](Self::profile), counting only cells still alive in `snapshot`
+    pub fn live_profile(&self, snapshot: &HeapSnapshot) -> Vec<AllocationSite> {
+        self.aggregate(|sample| snapshot.node(sample.node).is_some())
+    }
+    
+    fn aggregate(&self, keep: impl Fn(&AllocationSample) -> bool) -> Vec<AllocationSite> {
+        let mut sites: Vec<AllocationSite> = Vec::new();
+        for sample in self.samples.iter().filter(|s| keep(s)) {
+            let bytes = sample.size.max(self.interval);
+            match sites.iter_mut().find(|site| site.stack == sample.stack) {
+                Some(site) => {
+                    site.count += 1;
+                    site.bytes += bytes;
+                }
+                None => sites.push(AllocationSite { stack: sample.stack.clone(), count: 1, bytes }),
+            }
+        }
+        sites.sort_by_key(|site| std::cmp::Reverse(site.bytes));
+        sites
+    }
+}
+
+impl Default for AllocationSampler {
+    fn default() -> Self { Self::new(Self::DEFAULT_INTERVAL) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_snapshot_retainers_and_diff() {
+        // global `cache` -> [obj0, obj1]; obj0.next -> obj1
+        let mut objects = vec![JsObject::new(), JsObject::new()];
+        objects[0].set("next", JsVal::Object(1));
+        let mut arr = JsArray::new();
+        arr.push(JsVal::Object(0));
+        arr.push(JsVal::Object(1));
+        let arrays = vec![arr];
+        let cells = HeapCells { objects: &objects, arrays: &arrays, closures: &[] };
+        
+        let roots = [(EdgeName::Global("cache".into()), JsVal::Array(0))];
+        let after = HeapSnapshot::capture(&roots, cells);
+        assert_eq!(after.nodes().len(), 3);
+        assert_eq!(after.retainers(HeapNodeId::object(1)).count(), 2);
+        let path = after.retaining_path(HeapNodeId::object(1));
+        assert_eq!(path[0].name, EdgeName::Global("cache".into()));
+        assert_eq!(path[1].name, EdgeName::Element(1));
+        
+        // The array dominates both objects
+        let array = after.node(HeapNodeId::array(0)).unwrap();
+        assert_eq!(array.retained_size, after.total_size());
+        let obj0 = after.node(HeapNodeId::object(0)).unwrap();
+        assert_eq!(obj0.retained_size, obj0.self_size);
+        
+        let before = HeapSnapshot::capture(&[], cells);
+        let diff = after.diff(&before);
+        let object = diff.iter().find(|d| &*d.name == "Object").unwrap();
+        assert_eq!(object.count_delta(), 2);
+        assert!(diff.iter().all(|d| d.removed == 0));
+    }
+    
+    #[test]
+    fn test_allocation_sampling() {
+        let mut sampler = AllocationSampler::new(100);
+        let stack: Vec<Box<str>> = vec!["main".into(), "leak".into()];
+        for i in 0..10 {
+            sampler.record(HeapNodeId::object(i), 40, &stack);
+        }
+        sampler.record(HeapNodeId::array(0), 500, &[]);
+        
+        // Every third small object crosses the 100 byte interval; the large array always does
+        assert_eq!(sampler.samples().len(), 4);
+        let profile = sampler.profile();
+        assert_eq!(profile[0].bytes, 500);
+        assert_eq!(profile[1].stack, stack);
+        assert_eq!(profile[1].count, 3);
+        assert_eq!(profile[1].bytes, 300);
+        
+        let empty = HeapSnapshot::default();
+        assert!(sampler.live_profile(&empty).is_empty());
+    }
+}
@@ -35,6 +35,11 @@ impl<'src> Lexer<'src> {
         self.pos
     }
     
+    /// Source text of a token
+    pub fn text(&self, span: Span) -> &'src str {
+        self.source.get(span.start as usize..span.end as usize).unwrap_or("")
+    }
+    
     /// Get current line number
     pub fn line(&self) -> u32 {
         self.line
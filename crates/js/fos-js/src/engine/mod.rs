@@ -17,7 +17,7 @@
 //! - Proxy/Reflect: Meta-programming
 //! - Regex: Pattern matching
 //! - JSON: Parse/stringify
-//! - Date: Date object
+//! - Date: Date object, with tz database time zones
 
 // Core
 pub mod token;
@@ -28,6 +28,8 @@ pub mod bytecode;
 pub mod compiler;
 pub mod vm;
 pub mod gc;
+pub mod heap_snapshot;
+pub mod coverage;
 pub mod value;
 pub mod object;
 pub mod builtins;
@@ -78,6 +80,7 @@ pub mod json;
 pub mod symbol;
 pub mod collections;
 pub mod date;
+pub mod tz;
 pub mod typed_array;
 pub mod event_loop;
 pub mod proxy;
@@ -91,6 +94,8 @@ pub use bytecode::{Bytecode, Opcode};
 pub use compiler::Compiler;
 pub use vm::VirtualMachine;
 pub use gc::GarbageCollector;
+pub use heap_snapshot::{HeapSnapshot, HeapNode, HeapNodeId, HeapNodeKind, HeapEdge, EdgeName, ClassDelta, AllocationSampler, AllocationSite};
+pub use coverage::{CoverageCounters, ScriptCoverage, FunctionCoverage};
 pub use value::JsVal;
 pub use object::JsObject;
 pub use custom_engine::{CustomEngine, CustomContext};
@@ -109,7 +114,8 @@ pub use regex::{JsRegex, RegexFlags, RegexMatch};
 pub use json::{parse as json_parse, stringify as json_stringify};
 pub use symbol::{JsSymbol, SymbolRegistry, WellKnownSymbols};
 pub use collections::{JsMap, JsSet, JsWeakMap, JsWeakSet};
-pub use date::JsDate;
+pub use date::{JsDate, DateFields};
+pub use tz::{TimeZone, LocalTimeType};
 pub use typed_array::{ArrayBuffer, DataView, TypedArray, TypedArrayKind};
 pub use event_loop::EventLoop;
 pub use proxy::{JsProxy, ProxyHandler, Reflect};
@@ -1176,7 +1176,7 @@ impl<'src> Parser<'src> {
         loop {
             if self.check(&TokenKind::Dot) {
                 self.advance();
-                let property = self.parse_identifier()?;
+                let property = self.parse_property_name()?;
                 let span = self.ast.get(object).unwrap().span.merge(self.previous.span);
                 object = self.ast.add_node(AstNode::new(
                     AstNodeKind::MemberExpression { object, property, computed: false, optional: false },
@@ -1185,7 +1185,7 @@ impl<'src> Parser<'src> {
             } else if self.check(&TokenKind::QuestionDot) {
                 // Optional chaining ?.
                 self.advance();
-                let property = self.parse_identifier()?;
+                let property = self.parse_property_name()?;
                 let span = self.ast.get(object).unwrap().span.merge(self.previous.span);
                 object = self.ast.add_node(AstNode::new(
                     AstNodeKind::MemberExpression { object, property, computed: false, optional: true },
@@ -1561,6 +1561,27 @@ impl<'src> Parser<'src> {
         )))
     }
     
+    /// Property name after `.`, where keywords are names too (`obj.set`,
+    /// `promise.catch`)
+    fn parse_property_name(&mut self) -> Result<NodeId, ParseError> {
+        let kind = &self.current.kind;
+        let reserved = kind.is_keyword() || matches!(kind,
+            TokenKind::As | TokenKind::Async | TokenKind::From | TokenKind::Get |
+            TokenKind::Meta | TokenKind::Set | TokenKind::Static | TokenKind::Target |
+            TokenKind::Boolean(_) | TokenKind::Null | TokenKind::Undefined
+        );
+        if !reserved {
+            return self.parse_identifier();
+        }
+        let span = self.current.span;
+        let name: Box<str> = self.lexer.text(span).into();
+        self.advance();
+        Ok(self.ast.add_node(AstNode::new(
+            AstNodeKind::Identifier { name },
+            span,
+        )))
+    }
+    
     fn parse_identifier(&mut self) -> Result<NodeId, ParseError> {
         let span = self.current.span;
         if let TokenKind::Identifier(name) = &self.current.kind {
@@ -1614,4 +1635,11 @@ mod tests {
         let ast = parser.parse().unwrap();
         assert!(ast.root().is_some());
     }
+    
+    #[test]
+    fn test_keyword_property_names() {
+        let parser = Parser::new("storage.set({ a: 1 }); p.catch(f); m.delete(k); o?.default;");
+        let ast = parser.parse().unwrap();
+        assert!(ast.root().is_some());
+    }
 }
@@ -0,0 +1,568 @@
+//! Time Zones
+//!
+//! UTC offsets from the tz database, for Date and anything else that turns
+//! instants into wall-clock time. Zones load from TZif files (the system's
+//! zoneinfo, or bytes handed in), whose POSIX TZ footer covers instants
+//! past the last listed transition; a POSIX TZ string alone makes a zone
+//! too. The host zone comes from `TZ`, then `/etc/localtime`, then UTC.
+
+use std::sync::OnceLock;
+
+const MS_PER_SEC: f64 = 1000.0;
+const SECS_PER_DAY: i64 = 86_400;
+
+/// Directories searched for zoneinfo files, after `TZDIR`
+const ZONEINFO_DIRS: &[&str] = &["/usr/share/zoneinfo", "/usr/lib/zoneinfo", "/usr/share/lib/zoneinfo"];
+
+/// Offset, DST flag and abbreviation in effect at some instant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalTimeType {
+    /// Seconds east of UTC
+    pub offset: i32,
+    pub is_dst: bool,
+    /// Abbreviation such as "CET", or "+0530" for zones without one
+    pub abbreviation: String,
+}
+
+impl LocalTimeType {
+    fn new(offset: i32, is_dst: bool, abbreviation: &str) -> Self {
+        Self { offset, is_dst, abbreviation: abbreviation.to_string() }
+    }
+}
+
+/// Day a POSIX TZ rule switches on
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RuleDay {
+    /// `Jn`: day 1 to 365, never counting February 29
+    Julian1(u16),
+    /// `n`: day 0 to 365, counting February 29
+    Julian0(u16),
+    /// `Mm.w.d`: weekday `d` (0 is Sunday) of week `w` of month `m`; week
+    /// 5 is the last
+    MonthWeekDay { month: u8, week: u8, weekday: u8 },
+}
+
+impl RuleDay {
+    /// Days since the epoch of the rule's date in `year`
+    fn day_in(&self, year: i64) -> i64 {
+        let jan1 = days_from_civil(year, 1, 1);
+        match *self {
+            RuleDay::Julian1(n) => {
+                let leap_day = (is_leap_year(year) && n >= 60) as i64;
+                jan1 + n as i64 - 1 + leap_day
+            }
+            RuleDay::Julian0(n) => jan1 + n as i64,
+            RuleDay::MonthWeekDay { month, week, weekday } => {
+                let first = days_from_civil(year, month as u32, 1);
+                let first_weekday = weekday_of(first);
+                let mut day = (weekday as i64 - first_weekday).rem_euclid(7) + (week as i64 - 1) * 7;
+                while day >= days_in_month(year, month as u32) {
+                    day -= 7;
+                }
+                first + day
+            }
+        }
+    }
+}
+
+/// Daylight saving part of a POSIX TZ rule
+#[derive(Debug, Clone, PartialEq)]
+struct DstRule {
+    dst: LocalTimeType,
+    start: RuleDay,
+    /// Local standard time of day the switch to DST happens, in seconds
+    start_time: i32,
+    end: RuleDay,
+    /// Local DST time of day the switch back happens, in seconds
+    end_time: i32,
+}
+
+/// A POSIX TZ string such as `CET-1CEST,M3.5.0,M10.5.0/3`
+#[derive(Debug, Clone, PartialEq)]
+struct PosixRule {
+    std: LocalTimeType,
+    dst: Option<DstRule>,
+}
+
+impl PosixRule {
+    fn parse(s: &str) -> Option<Self> {
+        let mut p = RuleParser { s: s.as_bytes(), pos: 0 };
+        let std_name = p.name()?;
+        let std_offset = -p.offset()?;
+        let std = LocalTimeType::new(std_offset, false, &std_name);
+        if p.done() {
+            return Some(Self { std, dst: None });
+        }
+        
+        let dst_name = p.name()?;
+        let dst_offset = match p.peek() {
+            Some(b',') | None => std_offset + 3600,
+            _ => -p.offset()?,
+        };
+        let dst = LocalTimeType::new(dst_offset, true, &dst_name);
+        // Without a rule, the US rules since 2007
+        let (start, start_time, end, end_time) = if p.done() {
+            (RuleDay::MonthWeekDay { month: 3, week: 2, weekday: 0 }, 7200,
+             RuleDay::MonthWeekDay { month: 11, week: 1, weekday: 0 }, 7200)
+        } else {
+            p.expect(b',')?;
+            let (start, start_time) = p.rule_date()?;
+            p.expect(b',')?;
+            let (end, end_time) = p.rule_date()?;
+            (start, start_time, end, end_time)
+        };
+        p.done().then_some(Self { std, dst: Some(DstRule { dst, start, start_time, end, end_time }) })
+    }
+    
+    /// Local time type at `utc` seconds
+    fn local_type(&self, utc: i64) -> &LocalTimeType {
+        let Some(rule) = &self.dst else { return &self.std };
+        let (year, _, _) = civil_from_days((utc + self.std.offset as i64).div_euclid(SECS_PER_DAY));
+        let start = rule.start.day_in(year) * SECS_PER_DAY + rule.start_time as i64 - self.std.offset as i64;
+        let end = rule.end.day_in(year) * SECS_PER_DAY + rule.end_time as i64 - rule.dst.offset as i64;
+        let in_dst = if start < end {
+            start <= utc && utc < end
+        } else {
+            // Southern hemisphere: DST spans the new year
+            !(end <= utc && utc < start)
+        };
+        if in_dst { &rule.dst } else { &self.std }
+    }
+}
+
+/// Cursor over a POSIX TZ string
+struct RuleParser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl RuleParser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
+    }
+    
+    fn done(&self) -> bool {
+        self.pos == self.s.len()
+    }
+    
+    fn expect(&mut self, c: u8) -> Option<()> {
+        (self.peek() == Some(c)).then(|| self.pos += 1)
+    }
+    
+    /// `EST`, or quoted like `<+0330>`
+    fn name(&mut self) -> Option<String> {
+        let start = self.pos;
+        let name = if self.expect(b'<').is_some() {
+            while self.peek().is_some_and(|c| c != b'>') {
+                self.pos += 1;
+            }
+            let name = &self.s[start + 1..self.pos];
+            self.expect(b'>')?;
+            name
+        } else {
+            while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                self.pos += 1;
+            }
+            &self.s[start..self.pos]
+        };
+        (name.len() >= 3).then(|| String::from_utf8_lossy(name).into_owned())
+    }
+    
+    fn number(&mut self) -> Option<i32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.s[start..self.pos]).ok()?.parse().ok()
+    }
+    
+    /// `[+-]hh[:mm[:ss]]` in seconds
+    fn offset(&mut self) -> Option<i32> {
+        let sign = match self.peek() {
+            Some(b'-') => { self.pos += 1; -1 }
+            Some(b'+') => { self.pos += 1; 1 }
+            _ => 1,
+        };
+        let mut secs = self.number()? * 3600;
+        if self.expect(b':').is_some() {
+            secs += self.number()? * 60;
+            if self.expect(b':').is_some() {
+                secs += self.number()?;
+            }
+        }
+        Some(sign * secs)
+    }
+    
+    /// A rule date with its optional `/time`, 02:00 by default
+    fn rule_date(&mut self) -> Option<(RuleDay, i32)> {
+        let day = match self.peek()? {
+            b'J' => {
+                self.pos += 1;
+                RuleDay::Julian1(self.number().filter(|n| (1..=365).contains(n))? as u16)
+            }
+            b'M' => {
+                self.pos += 1;
+                let month = self.number().filter(|n| (1..=12).contains(n))? as u8;
+                self.expect(b'.')?;
+                let week = self.number().filter(|n| (1..=5).contains(n))? as u8;
+                self.expect(b'.')?;
+                let weekday = self.number().filter(|n| (0..=6).contains(n))? as u8;
+                RuleDay::MonthWeekDay { month, week, weekday }
+            }
+            _ => RuleDay::Julian0(self.number().filter(|n| (0..=365).contains(n))? as u16),
+        };
+        let time = if self.expect(b'/').is_some() { self.offset()? } else { 7200 };
+        Some((day, time))
+    }
+}
+
+/// A time zone: transitions between local time types, then a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeZone {
+    name: String,
+    /// UTC seconds of each transition and the type from then on, sorted
+    transitions: Vec<(i64, usize)>,
+    types: Vec<LocalTimeType>,
+    /// For instants after the last transition
+    rule: Option<PosixRule>,
+}
+
+impl TimeZone {
+    /// Coordinated Universal Time
+    pub fn utc() -> Self {
+        Self::fixed("UTC", 0)
+    }
+    
+    /// A zone always `offset` seconds east of UTC
+    pub fn fixed(name: &str, offset: i32) -> Self {
+        Self {
+            name: name.to_string(),
+            transitions: Vec::new(),
+            types: vec![LocalTimeType::new(offset, false, name)],
+            rule: None,
+        }
+    }
+    
+    /// A zone from a POSIX TZ string such as `EST5EDT,M3.2.0,M11.1.0`
+    pub fn posix(tz: &str) -> Option<Self> {
+        let rule = PosixRule::parse(tz)?;
+        Some(Self { name: tz.to_string(), transitions: Vec::new(), types: vec![rule.std.clone()], rule: Some(rule) })
+    }
+    
+    /// A zone from the contents of a TZif file (RFC 8536)
+    pub fn from_tzif(name: &str, data: &[u8]) -> Result<Self, String> {
+        let (header, mut pos) = TzifHeader::read(data, 0)?;
+        let mut time_size = 4;
+        let mut header = header;
+        if header.version >= 2 {
+            // Skip the 32-bit block for the 64-bit one after it
+            pos += header.block_len(4);
+            let (v2, next) = TzifHeader::read(data, pos)?;
+            header = v2;
+            pos = next;
+            time_size = 8;
+        }
+        let block = data.get(pos..pos + header.block_len(time_size)).ok_or("truncated TZif data")?;
+        
+        let times = &block[..header.time_count * time_size];
+        let indices = &block[times.len()..times.len() + header.time_count];
+        let infos = &block[times.len() + indices.len()..][..header.type_count * 6];
+        let chars = &block[times.len() + indices.len() + infos.len()..][..header.char_count];
+        
+        let mut types = Vec::with_capacity(header.type_count);
+        for info in infos.chunks_exact(6) {
+            let offset = i32::from_be_bytes([info[0], info[1], info[2], info[3]]);
+            let start = info[5] as usize;
+            let abbreviation = chars.get(start..)
+                .map(|rest| rest.split(|&c| c == 0).next().unwrap_or_default())
+                .ok_or("bad TZif abbreviation index")?;
+            types.push(LocalTimeType::new(offset, info[4] != 0, &String::from_utf8_lossy(abbreviation)));
+        }
+        if types.is_empty() {
+            return Err("TZif data has no local time types".into());
+        }
+        
+        let mut transitions = Vec::with_capacity(header.time_count);
+        for (time, &ty) in times.chunks_exact(time_size).zip(indices) {
+            let at = match time_size {
+                8 => i64::from_be_bytes(time.try_into().unwrap_or_default()),
+                _ => i32::from_be_bytes(time.try_into().unwrap_or_default()) as i64,
+            };
+            if ty as usize >= types.len() {
+                return Err("bad TZif type index".into());
+            }
+            transitions.push((at, ty as usize));
+        }
+        
+        // The footer, `\n<POSIX TZ>\n`, follows the 64-bit block
+        let footer = data.get(pos + block.len()..)
+            .filter(|_| header.version >= 2)
+            .and_then(|rest| std::str::from_utf8(rest).ok())
+            .and_then(|rest| rest.strip_prefix('\n'))
+            .and_then(|rest| rest.split('\n').next())
+            .filter(|tz| !tz.is_empty());
+        let rule = footer.and_then(PosixRule::parse);
+        
+        Ok(Self { name: name.to_string(), transitions, types, rule })
+    }
+    
+    /// A zone from the system's tz database, such as `Europe/Paris`
+    pub fn named(name: &str) -> Option<Self> {
+        let valid = !name.is_empty()
+            && !name.starts_with('/')
+            && name.split('/').all(|part| !part.is_empty() && part != "." && part != "..");
+        if !valid {
+            return None;
+        }
+        let dirs = std::env::var("TZDIR").ok().into_iter().chain(ZONEINFO_DIRS.iter().map(|d| d.to_string()));
+        for dir in dirs {
+            if let Ok(data) = std::fs::read(std::path::Path::new(&dir).join(name)) {
+                return Self::from_tzif(name, &data).ok();
+            }
+        }
+        None
+    }
+    
+    /// The host's zone, read once
+    pub fn host() -> &'static TimeZone {
+        static HOST: OnceLock<TimeZone> = OnceLock::new();
+        HOST.get_or_init(|| Self::from_env().unwrap_or_else(Self::utc))
+    }
+    
+    /// Zone named by `TZ` (a zone name, a file path or a POSIX TZ
+    /// string), or else `/etc/localtime`
+    fn from_env() -> Option<Self> {
+        if let Ok(tz) = std::env::var("TZ") {
+            let tz = tz.strip_prefix(':').unwrap_or(&tz);
+            if tz.starts_with('/') {
+                return std::fs::read(tz).ok().and_then(|data| Self::from_tzif(tz, &data).ok());
+            }
+            return Self::named(tz).or_else(|| Self::posix(tz));
+        }
+        let data = std::fs::read("/etc/localtime").ok()?;
+        // Name it after the zoneinfo file the link points at
+        let name = std::fs::read_link("/etc/localtime").ok()
+            .and_then(|target| {
+                let target = target.to_string_lossy().into_owned();
+                target.split_once("zoneinfo/").map(|(_, name)| name.to_string())
+            })
+            .unwrap_or_else(|| "localtime".to_string());
+        Self::from_tzif(&name, &data).ok()
+    }
+    
+    /// Zone name, such as `America/New_York`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    
+    /// Local time type in effect at `utc` seconds since the epoch
+    pub fn local_type(&self, utc: i64) -> &LocalTimeType {
+        let next = self.transitions.partition_point(|&(at, _)| at <= utc);
+        match (next.checked_sub(1).map(|i| self.transitions[i]), &self.rule) {
+            // Past the table, the footer rule takes over
+            (_, Some(rule)) if next == self.transitions.len() => rule.local_type(utc),
+            (Some((_, ty)), _) => &self.types[ty],
+            // Before the first transition: the first type
+            (None, _) => &self.types[0],
+        }
+    }
+    
+    /// Offset from UTC in milliseconds at a UTC time value
+    pub fn offset_ms(&self, utc_ms: f64) -> f64 {
+        self.local_type(to_secs(utc_ms)).offset as f64 * MS_PER_SEC
+    }
+    
+    /// UTC time value of a local time value. A wall-clock time that occurs
+    /// twice, when clocks go back, is the earlier instant; one skipped when
+    /// clocks go forward uses the offset from before the change, landing
+    /// after it, as ECMAScript specifies.
+    pub fn utc_ms(&self, local_ms: f64) -> f64 {
+        let day = SECS_PER_DAY as f64 * MS_PER_SEC;
+        let before = self.offset_ms(local_ms - day);
+        let after = self.offset_ms(local_ms + day);
+        let fits = |offset: f64| self.offset_ms(local_ms - offset) == offset;
+        match (fits(before), fits(after)) {
+            (true, true) => (local_ms - before).min(local_ms - after),
+            (false, true) => local_ms - after,
+            _ => local_ms - before,
+        }
+    }
+}
+
+/// Counts from a TZif header
+struct TzifHeader {
+    version: u8,
+    utc_count: usize,
+    std_count: usize,
+    leap_count: usize,
+    time_count: usize,
+    type_count: usize,
+    char_count: usize,
+}
+
+impl TzifHeader {
+    const LEN: usize = 44;
+    
+    fn read(data: &[u8], pos: usize) -> Result<(Self, usize), String> {
+        let header = data.get(pos..pos + Self::LEN).ok_or("truncated TZif header")?;
+        if &header[..4] != b"TZif" {
+            return Err("not TZif data".into());
+        }
+        let version = match header[4] {
+            0 => 1,
+            c @ b'2'..=b'9' => c - b'0',
+            _ => return Err("unknown TZif version".into()),
+        };
+        let count = |i: usize| u32::from_be_bytes([header[20 + i * 4], header[21 + i * 4], header[22 + i * 4], header[23 + i * 4]]) as usize;
+        Ok((Self {
+            version,
+            utc_count: count(0),
+            std_count: count(1),
+            leap_count: count(2),
+            time_count: count(3),
+            type_count: count(4),
+            char_count: count(5),
+        }, pos + Self::LEN))
+    }
+    
+    /// Length of the data block with `time_size`-byte times
+    fn block_len(&self, time_size: usize) -> usize {
+        self.time_count * (time_size + 1)
+            + self.type_count * 6
+            + self.char_count
+            + self.leap_count * (time_size + 4)
+            + self.std_count
+            + self.utc_count
+    }
+}
+
+/// Whole seconds of a time value, rounding down
+fn to_secs(ms: f64) -> i64 {
+    (ms / MS_PER_SEC).floor() as i64
+}
+
+pub(crate) fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub(crate) fn days_in_month(year: i64, month: u32) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date; `month` is 1-12
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Year, month (1-12) and day of days since 1970-01-01
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// Day of the week of days since the epoch, 0 for Sunday
+pub(crate) fn weekday_of(days: i64) -> i64 {
+    (days + 4).rem_euclid(7)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn utc(year: i64, month: u32, day: u32, hour: i64) -> i64 {
+        days_from_civil(year, month, day) * SECS_PER_DAY + hour * 3600
+    }
+    
+    /// TZif v2 data with the given transitions, types and footer
+    fn tzif(transitions: &[(i64, u8)], types: &[(i32, bool, &str)], footer: &str) -> Vec<u8> {
+        let mut chars = Vec::new();
+        let mut infos = Vec::new();
+        for &(offset, dst, abbr) in types {
+            infos.extend_from_slice(&offset.to_be_bytes());
+            infos.push(dst as u8);
+            infos.push(chars.len() as u8);
+            chars.extend_from_slice(abbr.as_bytes());
+            chars.push(0);
+        }
+        let header = |times: usize| {
+            let mut h = b"TZif2".to_vec();
+            h.extend_from_slice(&[0; 15]);
+            for count in [0, 0, 0, times, types.len(), chars.len()] {
+                h.extend_from_slice(&(count as u32).to_be_bytes());
+            }
+            h
+        };
+        // An empty 32-bit block, then the 64-bit one
+        let mut data = header(0);
+        data.extend_from_slice(&infos);
+        data.extend_from_slice(&chars);
+        data.extend(header(transitions.len()));
+        for &(at, _) in transitions {
+            data.extend_from_slice(&at.to_be_bytes());
+        }
+        data.extend(transitions.iter().map(|&(_, ty)| ty));
+        data.extend_from_slice(&infos);
+        data.extend_from_slice(&chars);
+        data.extend_from_slice(format!("\n{}\n", footer).as_bytes());
+        data
+    }
+    
+    #[test]
+    fn test_posix_rule() {
+        let tz = TimeZone::posix("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        // 2024: DST from March 10 07:00 UTC to November 3 06:00 UTC
+        assert_eq!(tz.local_type(utc(2024, 3, 10, 7) - 1).abbreviation, "EST");
+        assert_eq!(tz.local_type(utc(2024, 3, 10, 7)).offset, -4 * 3600);
+        assert!(tz.local_type(utc(2024, 11, 3, 6) - 1).is_dst);
+        assert!(!tz.local_type(utc(2024, 11, 3, 6)).is_dst);
+        
+        // Southern hemisphere, with a quoted name
+        let tz = TimeZone::posix("<-03>3").unwrap();
+        assert_eq!(tz.local_type(0).abbreviation, "-03");
+        let tz = TimeZone::posix("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert!(tz.local_type(utc(2024, 1, 15, 0)).is_dst);
+        assert!(!tz.local_type(utc(2024, 7, 15, 0)).is_dst);
+    }
+    
+    #[test]
+    fn test_tzif_and_local_times() {
+        // A zone that moved from +01:00 to +02:00 in 2000, then follows EU
+        // summer time
+        let data = tzif(
+            &[(utc(2000, 1, 1, 0), 1)],
+            &[(3600, false, "OLD"), (7200, false, "EET"), (10800, true, "EEST")],
+            "EET-2EEST,M3.5.0/3,M10.5.0/4",
+        );
+        let tz = TimeZone::from_tzif("Test/Zone", &data).unwrap();
+        assert_eq!(tz.local_type(utc(1999, 6, 1, 0)).abbreviation, "OLD");
+        assert_eq!(tz.local_type(utc(2024, 1, 1, 0)).abbreviation, "EET");
+        assert_eq!(tz.local_type(utc(2024, 7, 1, 0)).abbreviation, "EEST");
+        assert!(TimeZone::from_tzif("Bad", b"TZjunk").is_err());
+        
+        // 2024-03-31 03:30 local does not exist; 2024-10-27 03:30 happens twice
+        let ms = |secs: i64| secs as f64 * 1000.0;
+        let gap = ms(utc(2024, 3, 31, 3) + 1800);
+        assert_eq!(tz.utc_ms(gap), ms(utc(2024, 3, 31, 1) + 1800));
+        let overlap = ms(utc(2024, 10, 27, 3) + 1800);
+        assert_eq!(tz.utc_ms(overlap), ms(utc(2024, 10, 27, 0) + 1800));
+    }
+}
@@ -4,6 +4,7 @@
 
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use crate::JsValue;
 
 // NaN-boxing constants
 const QNAN: u64 = 0x7FFC_0000_0000_0000;
@@ -240,6 +241,32 @@ impl JsVal {
         else { JsValKind::Undefined }
     }
     
+    /// Convert to the embedder's value type
+    pub fn to_host(&self) -> JsValue {
+        match self.kind() {
+            JsValKind::Undefined => JsValue::Undefined,
+            JsValKind::Null => JsValue::Null,
+            JsValKind::Bool(b) => JsValue::Bool(b),
+            JsValKind::Number(n) => JsValue::Number(n),
+            JsValKind::String(s) => JsValue::String(s.to_string()),
+            JsValKind::Object(_) => JsValue::Object,
+            JsValKind::Array(_) => JsValue::Array,
+            JsValKind::Function(_) => JsValue::Function,
+        }
+    }
+    
+    /// Convert from the embedder's value type; objects, arrays and
+    /// functions carry no handle and become undefined
+    pub fn from_host(value: JsValue) -> Self {
+        match value {
+            JsValue::Null => JsVal::Null,
+            JsValue::Bool(b) => JsVal::Bool(b),
+            JsValue::Number(n) => JsVal::Number(n),
+            JsValue::String(s) => JsVal::String(s.into_boxed_str()),
+            JsValue::Undefined | JsValue::Object | JsValue::Array | JsValue::Function => JsVal::Undefined,
+        }
+    }
+    
     // Runtime operations
     #[inline]
     pub fn is_truthy(&self) -> bool {
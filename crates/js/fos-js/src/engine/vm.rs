@@ -8,8 +8,40 @@ use super::value::JsVal;
 use super::object::{JsObject, JsArray};
 use super::promise::{JsPromise, PromiseState};
 use super::event_loop::EventLoop;
-use std::collections::HashMap;
+use super::gc::GarbageCollector;
+use super::heap_snapshot::{self, EdgeName, HeapCells, HeapNodeId, HeapSnapshot};
+use super::coverage::{CoverageCounters, ScriptCoverage};
+use crate::host::{Callback, HostFunction, HostValue, MAX_DEPTH};
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Function IDs with this bit set are host functions rather than closures
+const NATIVE_FUNCTION: u32 = 1 << 31;
+
+/// Source of realm IDs, unique in the process
+static NEXT_REALM: AtomicU64 = AtomicU64::new(1);
+
+/// Host function that is handed the `this` value, for the methods and
+/// accessors of objects the embedder defines
+pub type NativeMethod = Arc<dyn Fn(&mut VirtualMachine, JsVal, &[JsVal]) -> Result<JsVal, String> + Send + Sync>;
+
+/// Host function registered with the VM
+#[derive(Clone)]
+enum Native {
+    /// Takes and returns copies of values
+    Function(HostFunction),
+    /// Works on VM values directly
+    Method(NativeMethod),
+}
+
+/// Getter and setter of an accessor property; assigning to one without
+/// a setter does nothing
+#[derive(Clone, Default)]
+struct Accessor {
+    get: Option<NativeMethod>,
+    set: Option<NativeMethod>,
+}
 
 /// Open upvalue - points to stack slot
 /// Closed upvalue - holds the value itself
@@ -24,6 +56,8 @@ pub enum Upvalue {
 pub struct Closure {
     pub function: Arc<CompiledFunction>,
     pub upvalues: Vec<Arc<Mutex<Upvalue>>>,
+    /// ID of the script that defined the function
+    pub script: u32,
 }
 
 /// Call frame for function execution
@@ -79,6 +113,12 @@ pub struct VirtualMachine {
     objects: Vec<JsObject>,
     arrays: Vec<JsArray>,
     closures: Vec<Arc<Closure>>,
+    /// Host functions registered by the embedder
+    natives: Vec<Native>,
+    /// Accessor properties by the ID of the object that holds them
+    accessors: HashMap<u32, HashMap<Box<str>, Accessor>>,
+    /// Identifies this VM in callbacks handed to the embedder
+    realm: u64,
     open_upvalues: Vec<Arc<Mutex<Upvalue>>>,
     frames: Vec<CallFrame>,
     try_handlers: Vec<TryHandler>,
@@ -90,6 +130,12 @@ pub struct VirtualMachine {
     // This/Super bindings
     this_binding: JsVal,
     super_binding: Option<JsVal>,
+    // Heap accounting
+    gc: GarbageCollector,
+    call_stack: Vec<Box<str>>, // Names of the functions being executed
+    // Code coverage
+    coverage: Option<CoverageCounters>,
+    scripts_run: u32,
 }
 
 impl Default for VirtualMachine {
@@ -104,6 +150,9 @@ impl VirtualMachine {
             objects: Vec::new(),
             arrays: Vec::new(),
             closures: Vec::new(),
+            natives: Vec::new(),
+            accessors: HashMap::new(),
+            realm: NEXT_REALM.fetch_add(1, Ordering::Relaxed),
             open_upvalues: Vec::new(),
             frames: Vec::new(),
             try_handlers: Vec::new(),
@@ -113,14 +162,30 @@ impl VirtualMachine {
             pending_microtasks: Vec::new(),
             this_binding: JsVal::Undefined, // Global scope this is undefined in strict mode
             super_binding: None,
+            gc: GarbageCollector::new(),
+            call_stack: Vec::new(),
+            coverage: None,
+            scripts_run: 0,
         }
     }
     
     /// Run top-level bytecode
     pub fn run(&mut self, bytecode: &Bytecode) -> Result<JsVal, String> {
+        self.run_script(bytecode, "", 0)
+    }
+    
+    /// Run a script's top-level bytecode; `url` and `source_len` describe
+    /// the script in coverage reports
+    pub fn run_script(&mut self, bytecode: &Bytecode, url: &str, source_len: usize) -> Result<JsVal, String> {
+        let script = self.scripts_run;
+        self.scripts_run += 1;
+        let slot = self.coverage.as_mut().map(|c| c.begin_script(script, url, source_len, bytecode));
         let mut ip = 0;
         
         while ip < bytecode.code.len() {
+            if let (Some(coverage), Some(slot)) = (&mut self.coverage, slot) {
+                coverage.mark(slot, ip);
+            }
             let op = bytecode.code[ip];
             ip += 1;
             
@@ -142,8 +207,10 @@ impl VirtualMachine {
                             let closure = Arc::new(Closure {
                                 function: Arc::new((**f).clone()),
                                 upvalues: Vec::new(),
+                                script,
                             });
                             let idx = self.closures.len() as u32;
+                            self.track_alloc(HeapNodeId::closure(idx), heap_snapshot::closure_size(&closure));
                             self.closures.push(closure);
                             self.stack.push(JsVal::Function(idx));
                         }
@@ -311,81 +378,15 @@ impl VirtualMachine {
                 }
                 
                 // Objects/Arrays
-                Opcode::NewObject => {
-                    let obj_id = self.objects.len() as u32;
-                    self.objects.push(JsObject::new());
-                    self.stack.push(JsVal::Object(obj_id));
-                }
-                Opcode::NewArray => {
-                    let count = self.read_u16(bytecode, &mut ip) as usize;
-                    let mut arr = JsArray::with_capacity(count);
-                    for _ in 0..count {
-                        arr.push(self.stack.pop().unwrap_or(JsVal::Undefined));
-                    }
-                    let arr_id = self.arrays.len() as u32;
-                    self.arrays.push(arr);
-                    self.stack.push(JsVal::Array(arr_id));
-                }
-                Opcode::GetProperty => {
-                    let name_idx = self.read_u16(bytecode, &mut ip) as usize;
-                    let name = &bytecode.names[name_idx];
-                    if let Some(obj_id) = self.stack.pop().and_then(|v| v.as_object_id()) {
-                        // Walk prototype chain
-                        let val = self.get_property_with_prototype(obj_id, name);
-                        self.stack.push(val);
-                    } else {
-                        self.stack.push(JsVal::Undefined);
-                    }
-                }
-                Opcode::SetProperty => {
-                    let name_idx = self.read_u16(bytecode, &mut ip) as usize;
-                    let name = bytecode.names[name_idx].to_string();
-                    let val = self.stack.pop().unwrap_or(JsVal::Undefined);
-                    if let Some(obj_id) = self.stack.last().and_then(|v| v.as_object_id()) {
-                        if let Some(obj) = self.objects.get_mut(obj_id as usize) {
-                            obj.set(&name, val);
-                        }
-                    }
-                }
-                Opcode::GetIndex => {
-                    let idx = self.stack.pop().unwrap_or(JsVal::Undefined).to_number() as usize;
-                    if let Some(arr_id) = self.stack.pop().and_then(|v| v.as_array_id()) {
-                        if let Some(arr) = self.arrays.get(arr_id as usize) {
-                            self.stack.push(arr.get(idx));
-                        } else {
-                            self.stack.push(JsVal::Undefined);
-                        }
-                    } else {
-                        self.stack.push(JsVal::Undefined);
-                    }
-                }
-                
-                Opcode::Call => {
-                    let argc = bytecode.code[ip] as usize;
-                    ip += 1;
-                    
-                    // Pop arguments in reverse order
-                    let mut args: Vec<JsVal> = Vec::with_capacity(argc);
-                    for _ in 0..argc {
-                        args.push(self.stack.pop().unwrap_or(JsVal::Undefined));
-                    }
-                    args.reverse();
-                    
-                    // Pop the callee
-                    let callee = self.stack.pop().unwrap_or(JsVal::Undefined);
-                    
-                    if let Some(func_id) = callee.as_function_id() {
-                        if let Some(closure) = self.closures.get(func_id as usize).cloned() {
-                            // Execute function
-                            let result = self.call_function(&closure, &args)?;
-                            self.stack.push(result);
-                        } else {
-                            self.stack.push(JsVal::Undefined);
-                        }
-                    } else {
-                        // Not callable
-                        self.stack.push(JsVal::Undefined);
-                    }
+                Opcode::NewObject
+                | Opcode::NewArray
+                | Opcode::GetProperty
+                | Opcode::SetProperty
+                | Opcode::SetMember
+                | Opcode::GetIndex
+                | Opcode::Call
+                | Opcode::CallMethod => {
+                    ip = self.object_op(op, bytecode, ip)?;
                 }
                 
                 Opcode::Return => {
@@ -430,12 +431,132 @@ impl VirtualMachine {
             (Bool(a), Bool(b)) => a == b,
             (Number(a), Number(b)) => a == b,
             (String(a), String(b)) => a == b,
+            // Objects, arrays and functions by identity
+            (Object(a), Object(b)) | (Array(a), Array(b)) | (Function(a), Function(b)) => a == b,
             _ => false,
         }
     }
     
+    /// Run an object or call instruction, shared by top-level code and
+    /// function bodies; returns the next instruction's offset
+    fn object_op(&mut self, op: u8, bytecode: &Bytecode, mut ip: usize) -> Result<usize, String> {
+        match Opcode::try_from(op).unwrap_or(Opcode::Halt) {
+            Opcode::NewObject => {
+                let obj = self.new_object();
+                self.stack.push(obj);
+            }
+            Opcode::NewArray => {
+                let count = self.read_u16(bytecode, &mut ip) as usize;
+                let mut arr = JsArray::with_capacity(count);
+                for _ in 0..count {
+                    arr.push(self.stack.pop().unwrap_or(JsVal::Undefined));
+                }
+                let arr_id = self.arrays.len() as u32;
+                self.track_alloc(HeapNodeId::array(arr_id), heap_snapshot::array_size(&arr));
+                self.arrays.push(arr);
+                self.stack.push(JsVal::Array(arr_id));
+            }
+            Opcode::GetProperty => {
+                let name_idx = self.read_u16(bytecode, &mut ip) as usize;
+                let obj = self.stack.pop().unwrap_or(JsVal::Undefined);
+                let val = self.get_member(obj, &bytecode.names[name_idx])?;
+                self.stack.push(val);
+            }
+            Opcode::SetProperty => {
+                // Object literals: the object stays on the stack
+                let name_idx = self.read_u16(bytecode, &mut ip) as usize;
+                let val = self.stack.pop().unwrap_or(JsVal::Undefined);
+                if let Some(&obj) = self.stack.last() {
+                    self.set_property(obj, &bytecode.names[name_idx], val);
+                }
+            }
+            Opcode::SetMember => {
+                let name_idx = self.read_u16(bytecode, &mut ip) as usize;
+                let val = self.stack.pop().unwrap_or(JsVal::Undefined);
+                let obj = self.stack.pop().unwrap_or(JsVal::Undefined);
+                self.set_member(obj, &bytecode.names[name_idx], val)?;
+                self.stack.push(val);
+            }
+            Opcode::GetIndex => {
+                let idx = self.stack.pop().unwrap_or(JsVal::Undefined).to_number() as usize;
+                let val = self.stack.pop()
+                    .and_then(|v| v.as_array_id())
+                    .and_then(|id| self.arrays.get(id as usize))
+                    .map_or(JsVal::Undefined, |arr| arr.get(idx));
+                self.stack.push(val);
+            }
+            op @ (Opcode::Call | Opcode::CallMethod) => {
+                let argc = bytecode.code[ip] as usize;
+                ip += 1;
+                
+                // Pop arguments in reverse order
+                let mut args: Vec<JsVal> = Vec::with_capacity(argc);
+                for _ in 0..argc {
+                    args.push(self.stack.pop().unwrap_or(JsVal::Undefined));
+                }
+                args.reverse();
+                
+                // Pop the callee, and for a method call the object it was read from
+                let callee = self.stack.pop().unwrap_or(JsVal::Undefined);
+                let result = if op == Opcode::CallMethod {
+                    let this = self.stack.pop().unwrap_or(JsVal::Undefined);
+                    self.call_with_this(this, callee, &args)?
+                } else {
+                    self.call_value(callee, &args)?
+                };
+                self.stack.push(result);
+            }
+            _ => {}
+        }
+        Ok(ip)
+    }
+    
+    /// Call a function value, closure or host function; calling anything
+    /// else gives undefined
+    fn call_value(&mut self, callee: JsVal, args: &[JsVal]) -> Result<JsVal, String> {
+        self.call_with_this(self.this_binding, callee, args)
+    }
+    
+    /// Call a function value with `this` bound to the given value
+    fn call_with_this(&mut self, this: JsVal, callee: JsVal, args: &[JsVal]) -> Result<JsVal, String> {
+        let Some(func_id) = callee.as_function_id() else {
+            // Not callable
+            return Ok(JsVal::Undefined);
+        };
+        
+        if func_id & NATIVE_FUNCTION != 0 {
+            return match self.natives.get((func_id & !NATIVE_FUNCTION) as usize).cloned() {
+                Some(Native::Function(native)) => {
+                    let args: Vec<HostValue> = args.iter().map(|arg| self.to_host_value(*arg, 0)).collect();
+                    let result = native(&args).map_err(|e| e.to_string())?;
+                    Ok(self.from_host_value(result))
+                }
+                Some(Native::Method(native)) => native(self, this, args),
+                None => Ok(JsVal::Undefined),
+            };
+        }
+        
+        match self.closures.get(func_id as usize).cloned() {
+            Some(closure) => {
+                let saved = std::mem::replace(&mut self.this_binding, this);
+                let result = self.call_function(&closure, args);
+                self.this_binding = saved;
+                result
+            }
+            None => Ok(JsVal::Undefined),
+        }
+    }
+    
     /// Execute a function call
     fn call_function(&mut self, closure: &Closure, args: &[JsVal]) -> Result<JsVal, String> {
+        let name = closure.function.name.clone().unwrap_or_else(|| "(anonymous)".into());
+        self.call_stack.push(name);
+        let result = self.execute_function(closure, args);
+        self.call_stack.pop();
+        result
+    }
+    
+    fn execute_function(&mut self, closure: &Closure, args: &[JsVal]) -> Result<JsVal, String> {
         let func = &closure.function;
         let bytecode = &func.bytecode;
         
@@ -448,8 +569,12 @@ impl VirtualMachine {
         }
         
         // Execute the function's bytecode
+        let slot = self.coverage.as_mut().and_then(|c| c.enter(closure.script, func));
         let mut ip = 0;
         while ip < bytecode.code.len() {
+            if let (Some(coverage), Some(slot)) = (&mut self.coverage, slot) {
+                coverage.mark(slot, ip);
+            }
             let op = bytecode.code[ip];
             ip += 1;
             
@@ -469,8 +594,10 @@ impl VirtualMachine {
                             let closure = Arc::new(Closure {
                                 function: Arc::new((**f).clone()),
                                 upvalues: Vec::new(),
+                                script: closure.script,
                             });
                             let idx = self.closures.len() as u32;
+                            self.track_alloc(HeapNodeId::closure(idx), heap_snapshot::closure_size(&closure));
                             self.closures.push(closure);
                             self.stack.push(JsVal::Function(idx));
                         }
@@ -572,19 +699,15 @@ impl VirtualMachine {
                     }
                 }
                 
-                Opcode::Call => {
-                    let argc = bytecode.code[ip] as usize;
-                    ip += 1;
-                    let mut call_args: Vec<JsVal> = Vec::with_capacity(argc);
-                    for _ in 0..argc { call_args.push(self.stack.pop().unwrap_or(JsVal::Undefined)); }
-                    call_args.reverse();
-                    let callee = self.stack.pop().unwrap_or(JsVal::Undefined);
-                    if let Some(fid) = callee.as_function_id() {
-                        if let Some(c) = self.closures.get(fid as usize).cloned() {
-                            let result = self.call_function(&c, &call_args)?;
-                            self.stack.push(result);
-                        } else { self.stack.push(JsVal::Undefined); }
-                    } else { self.stack.push(JsVal::Undefined); }
+                Opcode::NewObject
+                | Opcode::NewArray
+                | Opcode::GetProperty
+                | Opcode::SetProperty
+                | Opcode::SetMember
+                | Opcode::GetIndex
+                | Opcode::Call
+                | Opcode::CallMethod => {
+                    ip = self.object_op(op, bytecode, ip)?;
                 }
                 
                 Opcode::Return => {
@@ -661,23 +784,219 @@ impl VirtualMachine {
         Ok(JsVal::Undefined)
     }
     
-    /// Walk prototype chain to find property
-    fn get_property_with_prototype(&self, obj_id: u32, name: &str) -> JsVal {
-        let mut current_id = Some(obj_id);
+    /// Read a property, walking the prototype chain; an accessor found on
+    /// the way is called with the object as `this`
+    pub fn get_member(&mut self, obj: JsVal, name: &str) -> Result<JsVal, String> {
+        let mut current_id = obj.as_object_id();
         while let Some(id) = current_id {
-            if let Some(obj) = self.objects.get(id as usize) {
-                if let Some(val) = obj.get(name) {
-                    return val.clone();
-                }
-                current_id = obj.prototype();
-            } else {
-                break;
+            let Some(cell) = self.objects.get(id as usize) else { break };
+            if let Some(val) = cell.get(name) {
+                return Ok(*val);
+            }
+            if let Some(accessor) = self.accessors.get(&id).and_then(|a| a.get(name)) {
+                return match accessor.get.clone() {
+                    Some(get) => get(self, obj, &[]),
+                    None => Ok(JsVal::Undefined),
+                };
             }
+            current_id = cell.prototype();
         }
-        JsVal::Undefined
+        Ok(JsVal::Undefined)
+    }
+    
+    /// Assign a property; an accessor on the object or its prototypes
+    /// handles the assignment, otherwise it lands on the object itself
+    pub fn set_member(&mut self, obj: JsVal, name: &str, val: JsVal) -> Result<(), String> {
+        let mut current_id = obj.as_object_id();
+        while let Some(id) = current_id {
+            if let Some(accessor) = self.accessors.get(&id).and_then(|a| a.get(name)) {
+                return match accessor.set.clone() {
+                    Some(set) => set(self, obj, &[val]).map(|_| ()),
+                    None => Ok(()),
+                };
+            }
+            current_id = self.objects.get(id as usize).and_then(JsObject::prototype);
+        }
+        self.set_property(obj, name, val);
+        Ok(())
     }
     
     pub fn set_global(&mut self, name: &str, val: JsVal) { self.globals.insert(name.into(), val); }
+    
+    /// Register a host function; calling the returned value from a script
+    /// runs it, and an error it returns ends the script with that error
+    pub fn register_native(&mut self, func: HostFunction) -> JsVal {
+        let id = self.natives.len() as u32 | NATIVE_FUNCTION;
+        self.natives.push(Native::Function(func));
+        JsVal::Function(id)
+    }
+    
+    /// Register a host function that works on VM values and receives
+    /// `this`, as the methods of host objects do
+    pub fn register_method(&mut self, func: NativeMethod) -> JsVal {
+        let id = self.natives.len() as u32 | NATIVE_FUNCTION;
+        self.natives.push(Native::Method(func));
+        JsVal::Function(id)
+    }
+    
+    /// Define an accessor property on an object; false if the value is
+    /// not an object
+    pub fn define_accessor(&mut self, obj: JsVal, name: &str, get: Option<NativeMethod>, set: Option<NativeMethod>) -> bool {
+        match obj.as_object_id().filter(|&id| (id as usize) < self.objects.len()) {
+            Some(id) => {
+                self.accessors.entry(id).or_default().insert(name.into(), Accessor { get, set });
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Create an empty object for the embedder to fill in
+    pub fn new_object(&mut self) -> JsVal {
+        let obj_id = self.objects.len() as u32;
+        let obj = JsObject::new();
+        self.track_alloc(HeapNodeId::object(obj_id), heap_snapshot::object_size(&obj));
+        self.objects.push(obj);
+        JsVal::Object(obj_id)
+    }
+    
+    /// Whether an object has properties of its own
+    pub fn has_own_properties(&self, obj: JsVal) -> bool {
+        obj.as_object_id()
+            .and_then(|id| self.objects.get(id as usize))
+            .is_some_and(|cell| cell.keys().next().is_some())
+    }
+    
+    /// Create an empty object inheriting from `proto`, or from nothing if
+    /// that is not an object
+    pub fn new_object_with_prototype(&mut self, proto: JsVal) -> JsVal {
+        let obj = self.new_object();
+        if let Some(cell) = obj.as_object_id().and_then(|id| self.objects.get_mut(id as usize)) {
+            cell.set_prototype(proto.as_object_id());
+        }
+        obj
+    }
+    
+    /// Set a property of an object; false if the value is not an object
+    pub fn set_property(&mut self, obj: JsVal, name: &str, val: JsVal) -> bool {
+        match obj.as_object_id().and_then(|id| self.objects.get_mut(id as usize)) {
+            Some(obj) => {
+                obj.set(name, val);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    pub fn realm(&self) -> u64 { self.realm }
+    
+    /// Copy a value out for the embedder: objects and arrays with their
+    /// contents, functions as callbacks into this realm
+    pub fn to_host_value(&self, val: JsVal, depth: usize) -> HostValue {
+        if depth > MAX_DEPTH {
+            return HostValue::UNDEFINED;
+        }
+        if let Some(id) = val.as_object_id() {
+            let Some(obj) = self.objects.get(id as usize) else { return HostValue::UNDEFINED };
+            let properties = obj.keys()
+                .map(|key| (key.to_string(), self.to_host_value(obj.get(key).copied().unwrap_or(JsVal::Undefined), depth + 1)))
+                .collect();
+            HostValue::Object(properties)
+        } else if let Some(id) = val.as_array_id() {
+            let Some(arr) = self.arrays.get(id as usize) else { return HostValue::UNDEFINED };
+            HostValue::Array((0..arr.len()).map(|i| self.to_host_value(arr.get(i), depth + 1)).collect())
+        } else if let Some(id) = val.as_function_id() {
+            HostValue::Function(Callback::new(self.realm, id))
+        } else {
+            HostValue::Value(val.to_host())
+        }
+    }
+    
+    /// Create a value from the embedder's; callbacks from other realms
+    /// become undefined
+    pub fn from_host_value(&mut self, value: HostValue) -> JsVal {
+        match value {
+            HostValue::Value(value) => JsVal::from_host(value),
+            HostValue::Object(properties) => {
+                let obj = self.new_object();
+                for (key, value) in properties {
+                    let value = self.from_host_value(value);
+                    self.set_property(obj, &key, value);
+                }
+                obj
+            }
+            HostValue::Array(items) => {
+                let mut arr = JsArray::with_capacity(items.len());
+                for item in items {
+                    arr.push(self.from_host_value(item));
+                }
+                let arr_id = self.arrays.len() as u32;
+                self.track_alloc(HeapNodeId::array(arr_id), heap_snapshot::array_size(&arr));
+                self.arrays.push(arr);
+                JsVal::Array(arr_id)
+            }
+            HostValue::Function(callback) if callback.realm() == self.realm => JsVal::Function(callback.id()),
+            HostValue::Function(_) => JsVal::Undefined,
+        }
+    }
+    
+    /// Call a function a script handed to the embedder; an error if it
+    /// belongs to another realm
+    pub fn call_callback(&mut self, callback: &Callback, args: &[HostValue]) -> Result<HostValue, String> {
+        if callback.realm() != self.realm {
+            return Err("callback belongs to another realm".to_string());
+        }
+        let args: Vec<JsVal> = args.iter().map(|arg| self.from_host_value(arg.clone())).collect();
+        let result = self.call_value(JsVal::Function(callback.id()), &args)?;
+        Ok(self.to_host_value(result, 0))
+    }
+    
+    /// Account a new heap cell, sampling it with the current call stack
+    fn track_alloc(&mut self, node: HeapNodeId, bytes: usize) {
+        self.gc.record_object_alloc(node, bytes, &self.call_stack);
+    }
+    
+    pub fn gc(&self) -> &GarbageCollector { &self.gc }
+    pub fn gc_mut(&mut self) -> &mut GarbageCollector { &mut self.gc }
+    
+    /// Snapshot everything reachable from globals, the stack and `this`
+    pub fn heap_snapshot(&self) -> HeapSnapshot {
+        let cells = HeapCells { objects: &self.objects, arrays: &self.arrays, closures: &self.closures };
+        self.gc.snapshot(&self.roots(), cells)
+    }
+    
+    /// Cells reachable from globals, the stack and `this`, without the
+    /// cost of a full snapshot
+    pub fn reachable(&self) -> HashSet<HeapNodeId> {
+        let cells = HeapCells { objects: &self.objects, arrays: &self.arrays, closures: &self.closures };
+        heap_snapshot::reachable(&self.roots(), cells)
+    }
+    
+    fn roots(&self) -> Vec<(EdgeName, JsVal)> {
+        let mut globals: Vec<_> = self.globals.iter().collect();
+        globals.sort_by(|a, b| a.0.cmp(b.0));
+        globals.into_iter()
+            .map(|(name, val)| (EdgeName::Global(name.clone()), *val))
+            .chain(self.stack.iter().enumerate().map(|(i, val)| (EdgeName::Stack(i as u32), *val)))
+            .chain(std::iter::once((EdgeName::This, self.this_binding)))
+            .collect()
+    }
+    
+    /// Start counting function calls and executed instructions of the
+    /// scripts run from now on
+    pub fn start_coverage(&mut self) {
+        self.coverage = Some(CoverageCounters::new());
+    }
+    
+    /// Coverage counted so far, if coverage is on
+    pub fn coverage(&self) -> Option<&[ScriptCoverage]> {
+        self.coverage.as_ref().map(CoverageCounters::scripts)
+    }
+    
+    /// Stop counting and return the coverage
+    pub fn stop_coverage(&mut self) -> Option<Vec<ScriptCoverage>> {
+        self.coverage.take().map(|c| c.scripts().to_vec())
+    }
 }
 
 impl TryFrom<u8> for Opcode {
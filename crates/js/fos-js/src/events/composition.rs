@@ -0,0 +1,98 @@
+//! Composition Events
+//!
+//! compositionstart, compositionupdate and compositionend, fired while an
+//! input method composes text (CJK input, emoji pickers, dead keys).
+
+/// Composition event
+#[derive(Debug, Clone)]
+pub struct CompositionEvent {
+    pub event_type: CompositionEventType,
+    /// Text being composed; the committed text on `compositionend`
+    pub data: String,
+    /// Locale of the input method, when known
+    pub locale: String,
+    
+    // Event state
+    pub bubbles: bool,
+    pub cancelable: bool,
+    default_prevented: bool,
+    pub timestamp: f64,
+}
+
+/// Composition event types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompositionEventType {
+    CompositionStart,
+    CompositionUpdate,
+    CompositionEnd,
+}
+
+impl CompositionEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CompositionStart => "compositionstart",
+            Self::CompositionUpdate => "compositionupdate",
+            Self::CompositionEnd => "compositionend",
+        }
+    }
+}
+
+impl CompositionEvent {
+    fn new(event_type: CompositionEventType, data: &str) -> Self {
+        Self {
+            event_type,
+            data: data.to_string(),
+            locale: String::new(),
+            bubbles: true,
+            // Only compositionstart can be cancelled
+            cancelable: event_type == CompositionEventType::CompositionStart,
+            default_prevented: false,
+            timestamp: 0.0,
+        }
+    }
+    
+    /// Create a compositionstart event; `data` is the selected text the
+    /// composition replaces
+    pub fn start(data: &str) -> Self {
+        Self::new(CompositionEventType::CompositionStart, data)
+    }
+    
+    /// Create a compositionupdate event with the text composed so far
+    pub fn update(data: &str) -> Self {
+        Self::new(CompositionEventType::CompositionUpdate, data)
+    }
+    
+    /// Create a compositionend event with the committed text
+    pub fn end(data: &str) -> Self {
+        Self::new(CompositionEventType::CompositionEnd, data)
+    }
+    
+    /// Prevent default
+    pub fn prevent_default(&mut self) {
+        if self.cancelable {
+            self.default_prevented = true;
+        }
+    }
+    
+    pub fn default_prevented(&self) -> bool {
+        self.default_prevented
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_composition_events() {
+        let mut start = CompositionEvent::start("");
+        start.prevent_default();
+        assert!(start.default_prevented());
+        
+        let mut end = CompositionEvent::end("日本");
+        end.prevent_default();
+        assert!(!end.default_prevented());
+        assert_eq!(end.event_type.as_str(), "compositionend");
+        assert_eq!(end.data, "日本");
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! HTML5 drag and drop API implementation.
 
+use crate::webapi::blob::File;
+
 /// Drag event
 #[derive(Debug, Clone)]
 pub struct DragEvent {
@@ -48,8 +50,8 @@ pub struct DataTransfer {
     pub effect_allowed: EffectAllowed,
     /// Transferred items
     items: Vec<DataTransferItem>,
-    /// Files being dragged
-    pub files: Vec<FileInfo>,
+    /// Files being dragged; empty until they are dropped
+    pub files: Vec<File>,
 }
 
 /// Drop effect
@@ -91,15 +93,6 @@ pub enum DataTransferKind {
     File,
 }
 
-/// File info for drag operations
-#[derive(Debug, Clone)]
-pub struct FileInfo {
-    pub name: String,
-    pub size: u64,
-    pub mime_type: String,
-    pub last_modified: u64,
-}
-
 impl DataTransfer {
     /// Create empty data transfer
     pub fn new() -> Self {
@@ -108,7 +101,7 @@ impl DataTransfer {
     
     /// Set data for a type
     pub fn set_data(&mut self, mime_type: &str, data: &str) {
-        self.items.retain(|i| i.mime_type != mime_type);
+        self.items.retain(|i| i.kind == DataTransferKind::File || i.mime_type != mime_type);
         self.items.push(DataTransferItem {
             kind: DataTransferKind::String,
             mime_type: mime_type.to_string(),
@@ -119,22 +112,46 @@ impl DataTransfer {
     /// Get data for a type
     pub fn get_data(&self, mime_type: &str) -> Option<&str> {
         self.items.iter()
-            .find(|i| i.mime_type == mime_type)
+            .find(|i| i.kind == DataTransferKind::String && i.mime_type == mime_type)
             .map(|i| i.data.as_str())
     }
     
+    /// Announce a file whose contents can't be read yet, as during
+    /// `dragenter` and `dragover`: it shows in `types` but not `files`
+    pub fn add_file_item(&mut self, mime_type: &str) {
+        self.items.push(DataTransferItem {
+            kind: DataTransferKind::File,
+            mime_type: mime_type.to_string(),
+            data: String::new(),
+        });
+    }
+    
+    /// Add a dropped file
+    pub fn add_file(&mut self, file: File) {
+        self.add_file_item(file.mime_type());
+        self.files.push(file);
+    }
+    
     /// Clear data
     pub fn clear_data(&mut self, mime_type: Option<&str>) {
+        // Files stay; only string data can be cleared
         if let Some(mt) = mime_type {
-            self.items.retain(|i| i.mime_type != mt);
+            self.items.retain(|i| i.kind == DataTransferKind::File || i.mime_type != mt);
         } else {
-            self.items.clear();
+            self.items.retain(|i| i.kind == DataTransferKind::File);
         }
     }
     
-    /// Get types
+    /// Get types; any files show as a single `Files` entry
     pub fn types(&self) -> Vec<&str> {
-        self.items.iter().map(|i| i.mime_type.as_str()).collect()
+        let mut types: Vec<&str> = self.items.iter()
+            .filter(|i| i.kind == DataTransferKind::String)
+            .map(|i| i.mime_type.as_str())
+            .collect();
+        if self.items.iter().any(|i| i.kind == DataTransferKind::File) {
+            types.push("Files");
+        }
+        types
     }
 }
 
@@ -160,10 +177,11 @@ impl Default for DragEvent {
 }
 
 impl DragEvent {
-    /// Create a drag start event
-    pub fn drag_start(x: f64, y: f64) -> Self {
+    /// Create an event of `event_type` at a point
+    pub fn new(event_type: DragEventType, x: f64, y: f64, data: DataTransfer) -> Self {
         Self {
-            event_type: DragEventType::DragStart,
+            event_type,
+            data_transfer: data,
             client_x: x,
             client_y: y,
             page_x: x,
@@ -172,11 +190,10 @@ impl DragEvent {
         }
     }
     
-    /// Create a drop event
-    pub fn drop(x: f64, y: f64, data: DataTransfer) -> Self {
+    /// Create a drag start event
+    pub fn drag_start(x: f64, y: f64) -> Self {
         Self {
-            event_type: DragEventType::Drop,
-            data_transfer: data,
+            event_type: DragEventType::DragStart,
             client_x: x,
             client_y: y,
             page_x: x,
@@ -185,6 +202,11 @@ impl DragEvent {
         }
     }
     
+    /// Create a drop event
+    pub fn drop(x: f64, y: f64, data: DataTransfer) -> Self {
+        Self::new(DragEventType::Drop, x, y, data)
+    }
+    
     /// Prevent default
     pub fn prevent_default(&mut self) {
         self.default_prevented = true;
@@ -203,6 +225,13 @@ mod tests {
         
         assert_eq!(dt.get_data("text/plain"), Some("Hello"));
         assert_eq!(dt.types().len(), 2);
+        
+        dt.add_file_item("image/png");
+        assert_eq!(dt.types(), vec!["text/plain", "text/html", "Files"]);
+        assert!(dt.files.is_empty());
+        assert_eq!(dt.get_data("image/png"), None);
+        dt.clear_data(None);
+        assert_eq!(dt.types(), vec!["Files"]);
     }
     
     #[test]
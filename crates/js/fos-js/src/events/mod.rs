@@ -1,6 +1,6 @@
 //! Input Events Module
 //!
-//! Keyboard, mouse, touch, drag, and focus event handling.
+//! Keyboard, mouse, touch, drag, composition, and focus event handling.
 
 mod keyboard;
 mod mouse;
@@ -8,13 +8,15 @@ mod focus;
 mod clipboard;
 mod touch;
 mod drag;
+mod composition;
 
 pub use keyboard::{KeyboardEvent, Key, KeyModifiers};
 pub use mouse::{MouseEvent, MouseButton};
 pub use focus::{FocusEvent, FocusManager};
 pub use clipboard::{ClipboardEvent, ClipboardData};
 pub use touch::{TouchEvent, Touch, TouchEventType};
-pub use drag::{DragEvent, DataTransfer, DropEffect};
+pub use drag::{DragEvent, DragEventType, DataTransfer, DropEffect, EffectAllowed};
+pub use composition::{CompositionEvent, CompositionEventType};
 
 /// Base event trait
 pub trait Event {
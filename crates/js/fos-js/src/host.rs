@@ -0,0 +1,650 @@
+//! Embedder API
+//!
+//! Host functions and objects that embedders expose to scripts. Rust
+//! closures take typed arguments, converted from JavaScript through
+//! [`FromJs`], and return a result converted back through [`IntoJs`]; an
+//! argument of the wrong type is a TypeError. Objects and arrays cross in
+//! full as [`HostValue`]s, and functions as [`Callback`]s the embedder can
+//! call later.
+//!
+//! A [`HostApi`] lists globals and is installed per realm: into a page's
+//! context and into each of its isolated worlds separately. Realms share
+//! the closures, and so whatever state they capture, but not the objects.
+//!
+//! Host functions are `Send + Sync + 'static`, so they own or share what
+//! they capture and outlive no borrow. They run while the engine executes
+//! the calling script and must not call back into the same engine; to
+//! answer through a callback, queue it and call it once the script is done.
+
+use crate::{JsValue, JsError};
+use crate::engine::VirtualMachine;
+use crate::engine::value::JsVal;
+use std::sync::Arc;
+
+/// A script function handed to the embedder, callable in the realm it
+/// came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Callback {
+    realm: u64,
+    id: u32,
+}
+
+impl Callback {
+    pub(crate) fn new(realm: u64, id: u32) -> Self {
+        Self { realm, id }
+    }
+    
+    /// Realm the function belongs to
+    pub fn realm(&self) -> u64 {
+        self.realm
+    }
+    
+    pub(crate) fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+/// A value crossing between scripts and the embedder: a primitive, an
+/// object or array copied in full, or a function
+#[derive(Debug, Clone)]
+pub enum HostValue {
+    Value(JsValue),
+    /// Properties in definition order
+    Object(Vec<(String, HostValue)>),
+    Array(Vec<HostValue>),
+    Function(Callback),
+}
+
+/// Deepest nesting read from scripts or JSON
+pub(crate) const MAX_DEPTH: usize = 32;
+
+impl HostValue {
+    pub const UNDEFINED: HostValue = HostValue::Value(JsValue::Undefined);
+    
+    /// Build an object from properties
+    pub fn object<'a>(properties: impl IntoIterator<Item = (&'a str, HostValue)>) -> Self {
+        Self::Object(properties.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+    
+    /// The value of a primitive
+    pub fn as_value(&self) -> Option<&JsValue> {
+        match self {
+            Self::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+    
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_value().and_then(JsValue::as_string)
+    }
+    
+    pub fn as_number(&self) -> Option<f64> {
+        self.as_value().and_then(JsValue::as_number)
+    }
+    
+    pub fn as_bool(&self) -> Option<bool> {
+        self.as_value().and_then(JsValue::as_bool)
+    }
+    
+    pub fn is_undefined(&self) -> bool {
+        matches!(self, Self::Value(JsValue::Undefined))
+    }
+    
+    /// Property of an object
+    pub fn get(&self, key: &str) -> Option<&HostValue> {
+        match self {
+            Self::Object(properties) => properties.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+    
+    /// Serialize as JSON, as JSON.stringify does: functions and undefined
+    /// are left out of objects and are null in arrays
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+    
+    fn write_json(&self, out: &mut String) {
+        match self {
+            Self::Value(JsValue::Bool(b)) => out.push_str(if *b { "true" } else { "false" }),
+            Self::Value(JsValue::Number(n)) if n.is_finite() => out.push_str(&n.to_string()),
+            Self::Value(JsValue::String(s)) => write_json_string(s, out),
+            Self::Value(_) | Self::Function(_) => out.push_str("null"),
+            Self::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.write_json(out);
+                }
+                out.push(']');
+            }
+            Self::Object(properties) => {
+                out.push('{');
+                let mut first = true;
+                for (key, value) in properties {
+                    if value.is_undefined() || matches!(value, Self::Function(_)) {
+                        continue;
+                    }
+                    if !first {
+                        out.push(',');
+                    }
+                    first = false;
+                    write_json_string(key, out);
+                    out.push(':');
+                    value.write_json(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+    
+    /// Parse JSON; None if it is malformed or nested too deeply
+    pub fn from_json(text: &str) -> Option<Self> {
+        let mut parser = JsonParser { chars: text.chars().peekable() };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        parser.chars.peek().is_none().then_some(value)
+    }
+}
+
+impl From<JsValue> for HostValue {
+    fn from(value: JsValue) -> Self {
+        Self::Value(value)
+    }
+}
+
+fn write_json_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+    }
+    
+    fn expect(&mut self, word: &str) -> Option<()> {
+        word.chars().all(|c| self.chars.next() == Some(c)).then_some(())
+    }
+    
+    fn value(&mut self, depth: usize) -> Option<HostValue> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        self.skip_whitespace();
+        match *self.chars.peek()? {
+            'n' => self.expect("null").map(|_| JsValue::Null.into()),
+            't' => self.expect("true").map(|_| JsValue::Bool(true).into()),
+            'f' => self.expect("false").map(|_| JsValue::Bool(false).into()),
+            '"' => self.string().map(|s| JsValue::String(s).into()),
+            '[' => {
+                self.chars.next();
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&']').is_some() {
+                    return Some(HostValue::Array(items));
+                }
+                loop {
+                    items.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.chars.next()? {
+                        ',' => continue,
+                        ']' => return Some(HostValue::Array(items)),
+                        _ => return None,
+                    }
+                }
+            }
+            '{' => {
+                self.chars.next();
+                let mut properties = Vec::new();
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&'}').is_some() {
+                    return Some(HostValue::Object(properties));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(":")?;
+                    properties.push((key, self.value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.chars.next()? {
+                        ',' => continue,
+                        '}' => return Some(HostValue::Object(properties)),
+                        _ => return None,
+                    }
+                }
+            }
+            _ => {
+                let mut number = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                    number.push(c);
+                }
+                number.parse().ok().map(|n| JsValue::Number(n).into())
+            }
+        }
+    }
+    
+    fn string(&mut self) -> Option<String> {
+        if self.chars.next()? != '"' {
+            return None;
+        }
+        let mut s = String::new();
+        loop {
+            match self.chars.next()? {
+                '"' => return Some(s),
+                '\\' => match self.chars.next()? {
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'b' => s.push('\u{8}'),
+                    'f' => s.push('\u{c}'),
+                    'u' => {
+                        let hex: String = (0..4).filter_map(|_| self.chars.next()).collect();
+                        s.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?).unwrap_or('\u{fffd}'));
+                    }
+                    c => s.push(c),
+                },
+                c => s.push(c),
+            }
+        }
+    }
+}
+
+/// Conversion of a script value into a Rust argument
+pub trait FromJs: Sized {
+    fn from_js(value: &HostValue) -> Result<Self, JsError>;
+}
+
+/// Conversion of a Rust result into a script value
+pub trait IntoJs {
+    fn into_js(self) -> HostValue;
+}
+
+fn type_error(expected: &str, value: &HostValue) -> JsError {
+    let found = match value {
+        HostValue::Value(JsValue::Undefined) => "undefined",
+        HostValue::Value(JsValue::Null) => "null",
+        HostValue::Value(JsValue::Bool(_)) => "boolean",
+        HostValue::Value(JsValue::Number(_)) => "number",
+        HostValue::Value(JsValue::String(_)) => "string",
+        HostValue::Value(JsValue::Array) | HostValue::Array(_) => "array",
+        HostValue::Value(JsValue::Function) | HostValue::Function(_) => "function",
+        HostValue::Value(JsValue::Object) | HostValue::Object(_) => "object",
+    };
+    JsError::TypeError(format!("expected {}, got {}", expected, found))
+}
+
+impl FromJs for HostValue {
+    fn from_js(value: &HostValue) -> Result<Self, JsError> {
+        Ok(value.clone())
+    }
+}
+
+/// Primitives as they are; objects, arrays and functions without contents
+impl FromJs for JsValue {
+    fn from_js(value: &HostValue) -> Result<Self, JsError> {
+        Ok(match value {
+            HostValue::Value(value) => value.clone(),
+            HostValue::Object(_) => JsValue::Object,
+            HostValue::Array(_) => JsValue::Array,
+            HostValue::Function(_) => JsValue::Function,
+        })
+    }
+}
+
+impl FromJs for bool {
+    fn from_js(value: &HostValue) -> Result<Self, JsError> {
+        value.as_bool().ok_or_else(|| type_error("boolean", value))
+    }
+}
+
+impl FromJs for f64 {
+    fn from_js(value: &HostValue) -> Result<Self, JsError> {
+        value.as_number().ok_or_else(|| type_error("number", value))
+    }
+}
+
+impl FromJs for String {
+    fn from_js(value: &HostValue) -> Result<Self, JsError> {
+        value.as_str().map(str::to_string).ok_or_else(|| type_error("string", value))
+    }
+}
+
+impl FromJs for Callback {
+    fn from_js(value: &HostValue) -> Result<Self, JsError> {
+        match value {
+            HostValue::Function(callback) => Ok(*callback),
+            value => Err(type_error("function", value)),
+        }
+    }
+}
+
+impl<T: FromJs> FromJs for Vec<T> {
+    fn from_js(value: &HostValue) -> Result<Self, JsError> {
+        match value {
+            HostValue::Array(items) => items.iter().map(T::from_js).collect(),
+            value => Err(type_error("array", value)),
+        }
+    }
+}
+
+/// Integers take whole numbers in range only
+macro_rules! impl_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl FromJs for $ty {
+                fn from_js(value: &HostValue) -> Result<Self, JsError> {
+                    match value.as_number() {
+                        Some(n) if n.fract() == 0.0 && n >= <$ty>::MIN as f64 && n <= <$ty>::MAX as f64 => Ok(n as $ty),
+                        _ => Err(type_error(concat!("integer (", stringify!($ty), ")"), value)),
+                    }
+                }
+            }
+            
+            impl IntoJs for $ty {
+                fn into_js(self) -> HostValue {
+                    JsValue::Number(self as f64).into()
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i32, u32, i64, u64, usize);
+
+/// Optional arguments: undefined, null or missing is `None`
+impl<T: FromJs> FromJs for Option<T> {
+    fn from_js(value: &HostValue) -> Result<Self, JsError> {
+        match value {
+            HostValue::Value(JsValue::Undefined | JsValue::Null) => Ok(None),
+            value => T::from_js(value).map(Some),
+        }
+    }
+}
+
+impl IntoJs for HostValue {
+    fn into_js(self) -> HostValue {
+        self
+    }
+}
+
+impl IntoJs for JsValue {
+    fn into_js(self) -> HostValue {
+        self.into()
+    }
+}
+
+impl IntoJs for () {
+    fn into_js(self) -> HostValue {
+        HostValue::UNDEFINED
+    }
+}
+
+impl IntoJs for bool {
+    fn into_js(self) -> HostValue {
+        JsValue::Bool(self).into()
+    }
+}
+
+impl IntoJs for f64 {
+    fn into_js(self) -> HostValue {
+        JsValue::Number(self).into()
+    }
+}
+
+impl IntoJs for String {
+    fn into_js(self) -> HostValue {
+        JsValue::String(self).into()
+    }
+}
+
+impl IntoJs for &str {
+    fn into_js(self) -> HostValue {
+        JsValue::String(self.to_string()).into()
+    }
+}
+
+/// `None` is null
+impl<T: IntoJs> IntoJs for Option<T> {
+    fn into_js(self) -> HostValue {
+        self.map_or(JsValue::Null.into(), IntoJs::into_js)
+    }
+}
+
+impl<T: IntoJs> IntoJs for Vec<T> {
+    fn into_js(self) -> HostValue {
+        HostValue::Array(self.into_iter().map(IntoJs::into_js).collect())
+    }
+}
+
+/// Host function as the VM calls it
+pub type HostFunction = Arc<dyn Fn(&[HostValue]) -> Result<HostValue, JsError> + Send + Sync>;
+
+/// Rust closures usable as host functions: `Fn(A, B, ..) -> Result<R, JsError>`
+/// with up to six [`FromJs`] arguments and an [`IntoJs`] result. Missing
+/// arguments are undefined and extra ones are ignored.
+pub trait IntoHostFunction<Args> {
+    fn into_host_function(self) -> HostFunction;
+}
+
+macro_rules! impl_into_host_function {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> IntoHostFunction<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> Result<R, JsError> + Send + Sync + 'static,
+            R: IntoJs,
+            $($arg: FromJs,)*
+        {
+            #[allow(non_snake_case, unused_variables, unused_mut, unused_assignments)]
+            fn into_host_function(self) -> HostFunction {
+                Arc::new(move |args: &[HostValue]| {
+                    let mut index = 0;
+                    $(
+                        let $arg = $arg::from_js(args.get(index).unwrap_or(&HostValue::UNDEFINED))
+                            .map_err(|e| match e {
+                                JsError::TypeError(msg) => JsError::TypeError(format!("argument {}: {}", index + 1, msg)),
+                                e => e,
+                            })?;
+                        index += 1;
+                    )*
+                    self($($arg),*).map(IntoJs::into_js)
+                })
+            }
+        }
+    };
+}
+
+impl_into_host_function!();
+impl_into_host_function!(A);
+impl_into_host_function!(A, B);
+impl_into_host_function!(A, B, C);
+impl_into_host_function!(A, B, C, D);
+impl_into_host_function!(A, B, C, D, E);
+impl_into_host_function!(A, B, C, D, E, G);
+
+/// Property of a host object
+#[derive(Clone)]
+enum HostProperty {
+    Value(HostValue),
+    Function(HostFunction),
+    Object(HostObject),
+}
+
+/// Host object: functions, values and nested objects under names
+#[derive(Clone, Default)]
+pub struct HostObject {
+    properties: Vec<(String, HostProperty)>,
+}
+
+impl HostObject {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a method
+    pub fn function<Args, F: IntoHostFunction<Args>>(mut self, name: &str, func: F) -> Self {
+        self.properties.push((name.to_string(), HostProperty::Function(func.into_host_function())));
+        self
+    }
+    
+    /// Add a constant property
+    pub fn value(mut self, name: &str, value: impl IntoJs) -> Self {
+        self.properties.push((name.to_string(), HostProperty::Value(value.into_js())));
+        self
+    }
+    
+    /// Add a nested object
+    pub fn object(mut self, name: &str, object: HostObject) -> Self {
+        self.properties.push((name.to_string(), HostProperty::Object(object)));
+        self
+    }
+    
+    /// Create the object in a VM
+    fn build(&self, vm: &mut VirtualMachine) -> JsVal {
+        let obj = vm.new_object();
+        for (name, property) in &self.properties {
+            let value = property.build(vm);
+            vm.set_property(obj, name, value);
+        }
+        obj
+    }
+}
+
+impl HostProperty {
+    fn build(&self, vm: &mut VirtualMachine) -> JsVal {
+        match self {
+            Self::Value(value) => vm.from_host_value(value.clone()),
+            Self::Function(func) => vm.register_native(func.clone()),
+            Self::Object(object) => object.build(vm),
+        }
+    }
+}
+
+/// Globals an embedder adds to each realm it installs them in, e.g. an
+/// extension API object in every extension's isolated world
+#[derive(Clone, Default)]
+pub struct HostApi {
+    globals: HostObject,
+}
+
+impl HostApi {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a global function
+    pub fn function<Args, F: IntoHostFunction<Args>>(mut self, name: &str, func: F) -> Self {
+        self.globals = self.globals.function(name, func);
+        self
+    }
+    
+    /// Add a global constant
+    pub fn value(mut self, name: &str, value: impl IntoJs) -> Self {
+        self.globals = self.globals.value(name, value);
+        self
+    }
+    
+    /// Add a global object
+    pub fn object(mut self, name: &str, object: HostObject) -> Self {
+        self.globals = self.globals.object(name, object);
+        self
+    }
+    
+    /// Install the globals into a VM, replacing globals of the same names
+    pub(crate) fn install(&self, vm: &mut VirtualMachine) {
+        for (name, property) in &self.globals.properties {
+            let value = property.build(vm);
+            vm.set_global(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CustomEngine;
+    use crate::engine_trait::JsEngine;
+    use std::sync::Mutex;
+    
+    #[test]
+    fn test_host_functions() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let sink = log.clone();
+        let api = HostApi::new()
+            .function("add", |a: f64, b: f64| Ok(a + b))
+            .function("repeat", |s: String, n: Option<u32>| Ok(s.repeat(n.unwrap_or(2) as usize)))
+            .object("host", HostObject::new()
+                .value("version", "1.0")
+                .function("log", move |msg: String| {
+                    sink.lock().unwrap().push(msg);
+                    Ok(())
+                }));
+        
+        // Two realms share the closures
+        let page = CustomEngine::new();
+        let world = CustomEngine::new();
+        page.install_host_api(&api);
+        world.install_host_api(&api);
+        
+        assert_eq!(page.eval("add(2, 3);").unwrap().as_number(), Some(5.0));
+        assert_eq!(page.eval("repeat('ab');").unwrap().as_string(), Some("abab"));
+        assert_eq!(page.eval("typeof host.log;").unwrap().as_string(), Some("function"));
+        assert_eq!(world.eval("host.version;").unwrap().as_string(), Some("1.0"));
+        page.exec("host.log('from page');").unwrap();
+        world.exec("host.log('from world');").unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["from page", "from world"]);
+        
+        let err = page.eval("add('2', 3);").unwrap_err();
+        assert!(err.to_string().contains("argument 1: expected number, got string"), "{}", err);
+        assert!(page.eval("repeat('a', 1.5);").is_err());
+    }
+    
+    #[test]
+    fn test_objects_and_callbacks() {
+        let saved = Arc::new(Mutex::new(None));
+        let slot = saved.clone();
+        let api = HostApi::new()
+            .function("keep", move |value: HostValue, callback: Callback| {
+                *slot.lock().unwrap() = Some((value, callback));
+                Ok(())
+            })
+            .function("make", || Ok(HostValue::object([("name", "tab".into_js()), ("ids", vec![1u32, 2].into_js())])));
+        let page = CustomEngine::new();
+        let other = CustomEngine::new();
+        page.install_host_api(&api);
+        
+        assert_eq!(page.eval("t = make(); t.name;").unwrap().as_string(), Some("tab"));
+        page.exec("function reply(x) { return x; } keep({ n: 1, s: 'a' }, reply);").unwrap();
+        let (value, callback) = saved.lock().unwrap().take().unwrap();
+        assert_eq!(value.to_json(), r#"{"n":1,"s":"a"}"#);
+        
+        // The callback runs later, in its own realm only
+        let arg = HostValue::object([("greeting", "hi".into_js())]);
+        assert_eq!(page.call(&callback, &[arg.clone()]).unwrap().get("greeting").and_then(HostValue::as_str), Some("hi"));
+        assert!(other.call(&callback, &[arg]).is_err());
+        
+        let json = r#"{"a":[1,true,null,"x\"y"],"b":{"c":-2.5}}"#;
+        assert_eq!(HostValue::from_json(json).unwrap().to_json(), json);
+        assert!(HostValue::from_json("{\"a\":").is_none());
+    }
+}
@@ -4,11 +4,15 @@
 //!
 //! Features:
 //! - Pluggable engine architecture (trait-based)
+//! - Embedder API (host functions and objects with typed arguments)
 //! - Console API (log, warn, error)
 //! - Timers (setTimeout, setInterval)
-//! - DOM bindings (document.getElementById, createElement)
+//! - DOM bindings generated from interface descriptions (Node, Element,
+//!   Text, Document), plus document.cookie
 //! - Storage APIs (localStorage, sessionStorage, IndexedDB)
 //! - Navigation APIs (history, location)
+//! - Navigator (connection, with the data saver preference; hardware
+//!   concurrency and device memory)
 //! - Input events (keyboard, mouse, focus, clipboard)
 //! - Built-in objects (Promise, Map, Set, Symbol, Proxy)
 //! - Web APIs (URL, Blob, TextEncoder, AbortController, Geolocation)
@@ -23,6 +27,9 @@ pub mod engine;
 pub mod storage;
 pub mod history;
 pub mod location;
+pub mod navigator;
+pub mod host;
+pub mod document_cookie;
 pub mod worker;
 pub mod media;
 pub mod media_bindings;
@@ -32,6 +39,8 @@ pub mod webapi;
 pub mod idb;
 pub mod js_optimizations;
 pub mod dom_bindings;
+pub mod bindgen;
+pub mod dom_idl;
 pub mod event_bindings;
 pub mod compress;
 pub mod cow;
@@ -49,18 +58,24 @@ pub use timers::TimerManager;
 pub use storage::Storage;
 pub use history::HistoryManager;
 pub use location::LocationManager;
+pub use navigator::{NetworkConnection, DeviceInfo, AppBadge};
+pub use host::{HostApi, HostObject, HostValue, HostFunction, Callback, FromJs, IntoJs, IntoHostFunction};
+pub use bindgen::DomBindings;
+pub use dom_idl::Interface;
+pub use document_cookie::CookieManager;
 pub use events::{
     KeyboardEvent, Key, KeyModifiers, MouseEvent, MouseButton,
     FocusEvent, FocusManager, ClipboardEvent, ClipboardData,
-    TouchEvent, Touch, DragEvent, DataTransfer,
+    TouchEvent, Touch, DragEvent, DragEventType, DataTransfer,
+    CompositionEvent, CompositionEventType,
 };
-pub use builtins::{JsPromise, PromiseState, JsMap, JsSet, JsSymbol, JsProxy, JsBigInt, JsWeakRef, SharedArrayBuffer, AsyncModule, TlaModuleGraph};
+pub use builtins::{JsPromise, PromiseState, JsMap, JsSet, JsSymbol, JsProxy, JsBigInt, JsWeakRef, SharedArrayBuffer, AsyncModule, TlaModuleGraph, JsSegmenter};
 pub use webapi::{JsUrl, JsUrlSearchParams, TextEncoder, TextDecoder, Blob, File, AbortController, Geolocation, Notification, Permissions, FormData, FileReader};
 pub use idb::{IDBFactory, IDBDatabase, CacheStorage, CookieStore};
 pub use js_optimizations::{LazyCompiler, ConstantFolder, EscapeAnalyzer, BytecodeCache, HeapCompressor, SharedBuiltins};
 
 use std::sync::{Arc, Mutex};
-use fos_dom::Document;
+use fos_dom::{Document, NodeId};
 
 /// JavaScript value
 #[derive(Debug, Clone)]
@@ -177,11 +192,30 @@ impl Default for JsRuntime {
     }
 }
 
+/// Host objects behind a page's APIs. The page and its isolated worlds each
+/// have their own globals but share these, so they see the same DOM,
+/// cookies, storage and location.
+#[derive(Clone)]
+struct HostObjects {
+    document: Arc<Mutex<Document>>,
+    cookies: Arc<Mutex<CookieManager>>,
+    local_storage: Arc<Mutex<Storage>>,
+    session_storage: Arc<Mutex<Storage>>,
+    history: Arc<Mutex<HistoryManager>>,
+    location: Arc<Mutex<LocationManager>>,
+    connection: Arc<Mutex<NetworkConnection>>,
+    device: Arc<Mutex<DeviceInfo>>,
+    /// Badge last set by the page and not yet taken by the browser
+    app_badge: Arc<Mutex<Option<AppBadge>>>,
+}
+
 /// JavaScript context with all browser APIs installed
 pub struct JsContext {
     engine: Arc<CustomEngine>,
     context: CustomContext,
     timers: Arc<Mutex<TimerManager>>,
+    host: HostObjects,
+    dom: DomBindings,
 }
 
 impl JsContext {
@@ -192,29 +226,71 @@ impl JsContext {
     
     /// Create context with a specific URL
     pub fn with_url(document: Arc<Mutex<Document>>, url: &str) -> Result<Self, JsError> {
+        let jar = Arc::new(Mutex::new(fos_net::CookieJar::new()));
+        Self::with_cookies(document, url, CookieManager::new(jar, url))
+    }
+    
+    /// Create context whose document.cookie uses the given cookie state
+    pub fn with_cookies(document: Arc<Mutex<Document>>, url: &str, cookies: CookieManager) -> Result<Self, JsError> {
+        let host = HostObjects {
+            document,
+            cookies: Arc::new(Mutex::new(cookies)),
+            // Create storage
+            local_storage: Arc::new(Mutex::new(Storage::session())),
+            session_storage: Arc::new(Mutex::new(Storage::session())),
+            // Create history and location
+            history: Arc::new(Mutex::new(HistoryManager::new(url))),
+            location: Arc::new(Mutex::new(
+                LocationManager::new(url).unwrap_or_else(|_| LocationManager::new("about:blank").unwrap())
+            )),
+            connection: Arc::new(Mutex::new(NetworkConnection::default())),
+            device: Arc::new(Mutex::new(DeviceInfo::default())),
+            app_badge: Arc::new(Mutex::new(None)),
+        };
+        Self::with_host(host)
+    }
+    
+    /// Create an isolated world on the same page, as extension content
+    /// scripts run in: a separate VM with its own globals and builtins, so
+    /// neither side can read or replace the other's variables and functions,
+    /// bound to the same DOM, cookies, storage and location
+    pub fn create_isolated_world(&self) -> Result<Self, JsError> {
+        Self::with_host(self.host.clone())
+    }
+    
+    /// Create a VM and install the browser APIs over the host objects
+    fn with_host(host: HostObjects) -> Result<Self, JsError> {
         let engine = Arc::new(CustomEngine::new());
         let context = CustomContext::new(engine.clone());
         let timers = Arc::new(Mutex::new(TimerManager::new()));
         
-        // Create storage
-        let local_storage = Arc::new(Mutex::new(Storage::session()));
-        let session_storage = Arc::new(Mutex::new(Storage::session()));
-        
-        // Create history and location
-        let history_manager = Arc::new(Mutex::new(HistoryManager::new(url)));
-        let location_manager = Arc::new(Mutex::new(
-            LocationManager::new(url).unwrap_or_else(|_| LocationManager::new("about:blank").unwrap())
-        ));
-        
         // Install APIs using abstract interface
         console::install_console(&context)?;
         timers::install_timers(&context, timers.clone())?;
-        bindings::install_document(&context, document)?;
-        storage::install_storage(&context, local_storage, session_storage)?;
-        history::install_history(&context, history_manager)?;
-        location::install_location(&context, location_manager)?;
+        bindings::install_document(&context, host.cookies.clone())?;
+        storage::install_storage(&context, host.local_storage.clone(), host.session_storage.clone())?;
+        history::install_history(&context, host.history.clone())?;
+        location::install_location(&context, host.location.clone())?;
+        navigator::install_navigator(&context, host.connection.clone(), host.device.clone(), host.app_badge.clone())?;
+        let dom = engine.install_dom_bindings(host.document.clone());
         
-        Ok(Self { engine, context, timers })
+        Ok(Self { engine, context, timers, host, dom })
+    }
+    
+    /// Install an embedder's host functions and objects into this realm;
+    /// isolated worlds need their own installation
+    pub fn install_host_api(&self, api: &HostApi) {
+        self.engine.install_host_api(api);
+    }
+    
+    /// Call a function a script in this realm handed to the embedder
+    pub fn call(&self, callback: &Callback, args: &[HostValue]) -> Result<HostValue, JsError> {
+        self.engine.call(callback, args)
+    }
+    
+    /// Whether a callback belongs to this realm
+    pub fn owns(&self, callback: &Callback) -> bool {
+        callback.realm() == self.engine.realm()
     }
     
     /// Evaluate JavaScript code
@@ -227,6 +303,11 @@ impl JsContext {
         self.engine.exec(code)
     }
     
+    /// Execute a script loaded from `url`, which names it in coverage reports
+    pub fn exec_script(&self, code: &str, url: &str) -> Result<(), JsError> {
+        self.engine.exec_script(code, url)
+    }
+    
     /// Process ready timers
     pub fn process_timers(&self) -> Result<(), JsError> {
         let ready = self.timers.lock().unwrap().get_ready_timers();
@@ -242,6 +323,79 @@ impl JsContext {
     pub fn has_pending_timers(&self) -> bool {
         self.timers.lock().unwrap().has_pending()
     }
+    
+    /// Mirror the data saver preference in navigator.connection.saveData
+    pub fn set_save_data(&self, enabled: bool) {
+        self.host.connection.lock().unwrap().save_data = enabled;
+    }
+    
+    /// Current navigator.connection.saveData
+    pub fn save_data(&self) -> bool {
+        self.host.connection.lock().unwrap().save_data
+    }
+    
+    /// Set the hardware values and time zone the page sees
+    pub fn set_device_info(&self, device: DeviceInfo) {
+        *self.host.device.lock().unwrap() = device;
+    }
+    
+    /// Hardware values and time zone the page sees
+    pub fn device_info(&self) -> DeviceInfo {
+        self.host.device.lock().unwrap().clone()
+    }
+    
+    /// Badge the page set with navigator.setAppBadge or clearAppBadge
+    /// since the last call, if any
+    pub fn take_app_badge(&self) -> Option<AppBadge> {
+        self.host.app_badge.lock().unwrap().take()
+    }
+    
+    /// Take cookie warnings for the devtools console
+    pub fn take_cookie_warnings(&self) -> Vec<String> {
+        self.host.cookies.lock().unwrap().take_warnings()
+    }
+    
+    /// The user interacted with the page, which lets it request storage
+    /// access once
+    pub fn notify_user_activation(&self) {
+        self.host.cookies.lock().unwrap().notify_user_activation();
+    }
+    
+    /// Drop the DOM wrappers no script in this realm can reach any more;
+    /// returns the nodes they wrapped
+    pub fn sweep_dom_wrappers(&self) -> Vec<NodeId> {
+        self.engine.sweep_dom_wrappers(&self.dom)
+    }
+    
+    /// Snapshot the JS heap
+    pub fn heap_snapshot(&self) -> engine::HeapSnapshot {
+        self.engine.heap_snapshot()
+    }
+    
+    /// Start sampling allocations, about one per `interval` bytes
+    pub fn start_allocation_sampling(&self, interval: usize) {
+        self.engine.start_allocation_sampling(interval);
+    }
+    
+    /// Stop sampling allocations and return the samples
+    pub fn stop_allocation_sampling(&self) -> Option<engine::AllocationSampler> {
+        self.engine.stop_allocation_sampling()
+    }
+    
+    /// Start counting which functions run
+    pub fn start_coverage(&self) {
+        self.engine.start_coverage();
+    }
+    
+    /// Coverage of the scripts run since coverage started
+    pub fn take_coverage(&self) -> Vec<engine::ScriptCoverage> {
+        self.engine.take_coverage()
+    }
+    
+    /// Stop counting and return the coverage
+    pub fn stop_coverage(&self) -> Option<Vec<engine::ScriptCoverage>> {
+        self.engine.stop_coverage()
+    }
 }
 
 #[cfg(test)]
@@ -281,4 +435,24 @@ mod tests {
             _ => panic!("Expected number"),
         }
     }
+    
+    #[test]
+    fn test_isolated_world() {
+        let document = Arc::new(Mutex::new(Document::new("https://example.com")));
+        let page = JsContext::with_url(document, "https://example.com").unwrap();
+        let world = page.create_isolated_world().unwrap();
+        
+        page.exec("secret = 42; function send() { return 'page'; }").unwrap();
+        assert_eq!(world.eval("typeof secret;").unwrap().as_string(), Some("undefined"));
+        assert_eq!(world.eval("typeof send;").unwrap().as_string(), Some("undefined"));
+        
+        // Replacing a function in one world leaves the other's alone
+        world.exec("function send() { return 'world'; }").unwrap();
+        assert_eq!(page.eval("send();").unwrap().as_string(), Some("page"));
+        assert_eq!(world.eval("send();").unwrap().as_string(), Some("world"));
+        
+        // Both are on the same page
+        page.set_save_data(true);
+        assert!(world.save_data());
+    }
 }
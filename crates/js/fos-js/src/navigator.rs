@@ -0,0 +1,191 @@
+//! Navigator API
+//!
+//! Implements navigator.connection (Network Information API), whose
+//! saveData flag mirrors the browser's data saver preference, and
+//! navigator.hardwareConcurrency and deviceMemory, which the browser clamps
+//! or coarsens against fingerprinting, and navigator.globalPrivacyControl.
+//! navigator.setAppBadge and clearAppBadge badge an installed web app's
+//! icon; the browser picks up changes and ignores them for other pages.
+
+use crate::{JsValue, JsError};
+use crate::engine::TimeZone;
+use crate::engine_trait::JsContextApi;
+use std::sync::{Arc, Mutex};
+
+/// Connection state exposed as navigator.connection
+#[derive(Debug, Clone)]
+pub struct NetworkConnection {
+    /// User turned on the data saver
+    pub save_data: bool,
+    /// "slow-2g", "2g", "3g" or "4g"
+    pub effective_type: String,
+    /// Estimated bandwidth in megabits per second
+    pub downlink: f64,
+    /// Estimated round-trip time in milliseconds
+    pub rtt: u32,
+}
+
+impl Default for NetworkConnection {
+    fn default() -> Self {
+        Self {
+            save_data: false,
+            effective_type: "4g".to_string(),
+            downlink: 10.0,
+            rtt: 50,
+        }
+    }
+}
+
+/// What a page can learn about the device and the user's privacy
+/// preferences, as the browser chooses to report it
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    /// navigator.hardwareConcurrency
+    pub hardware_concurrency: u32,
+    /// navigator.deviceMemory, in GiB
+    pub device_memory: f64,
+    /// Time zone the page's dates are shown in
+    pub time_zone: TimeZone,
+    /// navigator.globalPrivacyControl
+    pub global_privacy_control: bool,
+}
+
+impl Default for DeviceInfo {
+    fn default() -> Self {
+        Self {
+            hardware_concurrency: std::thread::available_parallelism().map_or(1, |n| n.get().min(16) as u32),
+            device_memory: 8.0,
+            time_zone: TimeZone::host().clone(),
+            global_privacy_control: false,
+        }
+    }
+}
+
+/// Badge on an installed web app's icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AppBadge {
+    /// No badge
+    #[default]
+    None,
+    /// A dot or other mark without a number
+    Flag,
+    /// A number, never zero
+    Count(u64),
+}
+
+impl AppBadge {
+    /// Badge for navigator.setAppBadge's argument: a flag without one,
+    /// cleared by zero; negative and non-finite numbers are a TypeError
+    pub fn from_js(contents: Option<&JsValue>) -> Result<Self, JsError> {
+        let count = match contents {
+            None | Some(JsValue::Undefined) => return Ok(Self::Flag),
+            Some(JsValue::Number(n)) => *n,
+            Some(JsValue::String(s)) => s.trim().parse().unwrap_or(f64::NAN),
+            Some(JsValue::Bool(b)) => *b as u8 as f64,
+            Some(JsValue::Null) => 0.0,
+            Some(_) => f64::NAN,
+        };
+        if !count.is_finite() || count < 0.0 {
+            return Err(JsError::TypeError("setAppBadge: contents must be a non-negative number".into()));
+        }
+        // Counts are unsigned long longs
+        let count = count.trunc().min(u64::MAX as f64) as u64;
+        Ok(if count == 0 { Self::None } else { Self::Count(count) })
+    }
+}
+
+/// Install navigator API into global
+pub fn install_navigator<C: JsContextApi>(
+    ctx: &C,
+    connection: Arc<Mutex<NetworkConnection>>,
+    device: Arc<Mutex<DeviceInfo>>,
+    badge: Arc<Mutex<Option<AppBadge>>>,
+) -> Result<(), JsError> {
+    let obj = ctx.create_object()?;
+    
+    // connection.getSaveData
+    let c = connection.clone();
+    ctx.set_function(&obj, "getSaveData", move |_args| {
+        Ok(JsValue::Bool(c.lock().unwrap().save_data))
+    })?;
+    
+    // connection.getEffectiveType
+    let c = connection.clone();
+    ctx.set_function(&obj, "getEffectiveType", move |_args| {
+        Ok(JsValue::String(c.lock().unwrap().effective_type.clone()))
+    })?;
+    
+    // connection.getDownlink
+    let c = connection.clone();
+    ctx.set_function(&obj, "getDownlink", move |_args| {
+        Ok(JsValue::Number(c.lock().unwrap().downlink))
+    })?;
+    
+    // connection.getRtt
+    let c = connection.clone();
+    ctx.set_function(&obj, "getRtt", move |_args| {
+        Ok(JsValue::Number(c.lock().unwrap().rtt as f64))
+    })?;
+    
+    // navigator.getHardwareConcurrency
+    let d = device.clone();
+    ctx.set_function(&obj, "getHardwareConcurrency", move |_args| {
+        Ok(JsValue::Number(d.lock().unwrap().hardware_concurrency as f64))
+    })?;
+    
+    // navigator.getDeviceMemory
+    let d = device.clone();
+    ctx.set_function(&obj, "getDeviceMemory", move |_args| {
+        Ok(JsValue::Number(d.lock().unwrap().device_memory))
+    })?;
+    
+    // navigator.getGlobalPrivacyControl
+    let d = device.clone();
+    ctx.set_function(&obj, "getGlobalPrivacyControl", move |_args| {
+        Ok(JsValue::Bool(d.lock().unwrap().global_privacy_control))
+    })?;
+    
+    // navigator.setAppBadge, recorded for the browser to pick up
+    let b = badge.clone();
+    ctx.set_function(&obj, "setAppBadge", move |args| {
+        *b.lock().unwrap() = Some(AppBadge::from_js(args.first())?);
+        Ok(JsValue::Undefined)
+    })?;
+    
+    // navigator.clearAppBadge
+    let b = badge.clone();
+    ctx.set_function(&obj, "clearAppBadge", move |_args| {
+        *b.lock().unwrap() = Some(AppBadge::None);
+        Ok(JsValue::Undefined)
+    })?;
+    
+    ctx.set_global("navigator", JsValue::Object)?;
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CustomContext, CustomEngine};
+    
+    #[test]
+    fn test_install_navigator() {
+        let ctx = CustomContext::new(Arc::new(CustomEngine::new()));
+        let connection = Arc::new(Mutex::new(NetworkConnection::default()));
+        let device = Arc::new(Mutex::new(DeviceInfo::default()));
+        install_navigator(&ctx, connection, device, Arc::default()).unwrap();
+        
+        assert!(matches!(ctx.get_global("navigator").unwrap(), JsValue::Object));
+    }
+    
+    #[test]
+    fn test_app_badge_contents() {
+        assert_eq!(AppBadge::from_js(None).unwrap(), AppBadge::Flag);
+        assert_eq!(AppBadge::from_js(Some(&JsValue::Number(3.7))).unwrap(), AppBadge::Count(3));
+        assert_eq!(AppBadge::from_js(Some(&JsValue::Number(0.0))).unwrap(), AppBadge::None);
+        assert_eq!(AppBadge::from_js(Some(&JsValue::String("12".into()))).unwrap(), AppBadge::Count(12));
+        assert!(AppBadge::from_js(Some(&JsValue::Number(-1.0))).is_err());
+        assert!(AppBadge::from_js(Some(&JsValue::Number(f64::INFINITY))).is_err());
+    }
+}
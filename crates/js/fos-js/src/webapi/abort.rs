@@ -1,8 +1,12 @@
 //! AbortController and AbortSignal
 //!
-//! Request cancellation mechanism.
+//! Request cancellation mechanism. A signal hands fos-net a `Deadline`,
+//! so timeouts and aborts reach requests already on the wire.
 
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use fos_net::{CancelToken, Deadline};
 
 /// AbortController - cancellation controller
 #[derive(Debug, Clone)]
@@ -21,6 +25,12 @@ struct AbortSignalInner {
     aborted: bool,
     reason: Option<String>,
     listeners: Vec<u32>, // callback IDs
+    /// When a timeout signal aborts itself
+    expires_at: Option<Instant>,
+    /// Signals from `any()` that abort along with this one
+    dependents: Vec<Weak<Mutex<AbortSignalInner>>>,
+    /// Cancels the requests this signal was given to
+    token: CancelToken,
 }
 
 impl AbortController {
@@ -55,6 +65,9 @@ impl AbortSignal {
                 aborted: false,
                 reason: None,
                 listeners: Vec::new(),
+                expires_at: None,
+                dependents: Vec::new(),
+                token: CancelToken::new(),
             })),
         }
     }
@@ -62,20 +75,58 @@ impl AbortSignal {
     /// Create an already-aborted signal
     pub fn aborted(reason: Option<&str>) -> Self {
         let signal = Self::new();
-        signal.inner.lock().unwrap().aborted = true;
-        signal.inner.lock().unwrap().reason = reason.map(|s| s.to_string());
+        signal.abort(reason);
         signal
     }
     
-    /// Create a signal that aborts after timeout
-    pub fn timeout(_ms: u64) -> Self {
-        // Would set up timer
-        Self::new()
+    /// Create a signal that aborts with a `TimeoutError` after `ms`
+    pub fn timeout(ms: u64) -> Self {
+        let signal = Self::new();
+        signal.inner.lock().unwrap().expires_at = Some(Instant::now() + Duration::from_millis(ms));
+        signal
+    }
+    
+    /// Create a signal that aborts when any of `signals` does, with its reason
+    pub fn any(signals: &[AbortSignal]) -> Self {
+        let combined = Self::new();
+        for signal in signals {
+            if signal.is_aborted() {
+                combined.abort(signal.reason().as_deref());
+                return combined;
+            }
+        }
+        
+        let expires_at = signals.iter()
+            .filter_map(|signal| signal.inner.lock().unwrap().expires_at)
+            .min();
+        combined.inner.lock().unwrap().expires_at = expires_at;
+        for signal in signals {
+            signal.inner.lock().unwrap().dependents.push(Arc::downgrade(&combined.inner));
+        }
+        combined
     }
     
     /// Check if aborted
     pub fn is_aborted(&self) -> bool {
-        self.inner.lock().unwrap().aborted
+        let expired = {
+            let inner = self.inner.lock().unwrap();
+            if inner.aborted {
+                return true;
+            }
+            inner.expires_at.is_some_and(|at| Instant::now() >= at)
+        };
+        if expired {
+            self.abort(Some("TimeoutError"));
+        }
+        expired
+    }
+    
+    /// Deadline for fos-net requests made with this signal: a timeout
+    /// bounds every phase, and aborting stops the request at its next wakeup
+    pub fn deadline(&self) -> Deadline {
+        let inner = self.inner.lock().unwrap();
+        inner.expires_at.map(Deadline::at).unwrap_or_default()
+            .with_token(inner.token.clone())
     }
     
     /// Get abort reason
@@ -102,21 +153,29 @@ impl AbortSignal {
     pub fn throw_if_aborted(&self) -> Result<(), AbortError> {
         if self.is_aborted() {
             Err(AbortError {
-                reason: self.reason().unwrap_or_default(),
+                reason: self.reason().unwrap_or_else(|| "AbortError".to_string()),
             })
         } else {
             Ok(())
         }
     }
     
-    /// Abort the signal
+    /// Abort the signal and the signals depending on it
     fn abort(&self, reason: Option<&str>) {
-        let mut inner = self.inner.lock().unwrap();
-        if !inner.aborted {
+        let dependents = {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.aborted {
+                return;
+            }
             inner.aborted = true;
             inner.reason = reason.map(|s| s.to_string());
+            inner.token.cancel();
             // Would invoke all listeners
             inner.listeners.clear();
+            std::mem::take(&mut inner.dependents)
+        };
+        for dependent in dependents.iter().filter_map(Weak::upgrade) {
+            AbortSignal { inner: dependent }.abort(reason);
         }
     }
 }
@@ -158,4 +217,26 @@ mod tests {
         let result = signal.throw_if_aborted();
         assert!(result.is_err());
     }
+    
+    #[test]
+    fn test_timeout_and_any() {
+        let timeout = AbortSignal::timeout(0);
+        assert!(timeout.is_aborted());
+        assert_eq!(timeout.reason().as_deref(), Some("TimeoutError"));
+        
+        let controller = AbortController::new();
+        let later = AbortSignal::timeout(60_000);
+        let any = AbortSignal::any(&[controller.signal().clone(), later.clone()]);
+        assert!(!any.is_aborted());
+        assert_eq!(any.deadline().expires_at(), later.deadline().expires_at());
+        
+        let deadline = any.deadline();
+        controller.abort(Some("User cancelled"));
+        assert!(any.is_aborted());
+        assert_eq!(any.reason().as_deref(), Some("User cancelled"));
+        assert!(deadline.is_cancelled());
+        assert!(!later.is_aborted());
+        
+        assert!(AbortSignal::any(&[timeout]).is_aborted());
+    }
 }
@@ -2,7 +2,9 @@
 //!
 //! Binary data handling for web content.
 
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
 
 /// Blob - immutable raw binary data
 #[derive(Debug, Clone)]
@@ -43,6 +45,11 @@ impl Blob {
         &self.data
     }
     
+    /// Shared handle to the bytes, for streaming them out without a copy
+    pub fn shared_bytes(&self) -> Arc<Vec<u8>> {
+        self.data.clone()
+    }
+    
     /// Slice the blob
     pub fn slice(&self, start: usize, end: Option<usize>, content_type: Option<&str>) -> Blob {
         let end = end.unwrap_or(self.data.len()).min(self.data.len());
@@ -107,6 +114,22 @@ impl File {
         }
     }
     
+    /// Read a file from disk, as when the user picks or drops it
+    pub fn from_path(path: &Path) -> std::io::Result<Self> {
+        let data = std::fs::read(path)?;
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let last_modified = std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_millis() as u64);
+        let options = FileOptions {
+            mime_type: Some(mime_type_for_name(&name).to_string()),
+            last_modified,
+        };
+        Ok(Self::new(vec![BlobPart::Bytes(data)], &name, options))
+    }
+    
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -128,6 +151,38 @@ impl File {
     }
 }
 
+/// MIME type of a file going by its extension; empty when unknown, as
+/// `File.type` is for files the browser can't identify
+pub fn mime_type_for_name(name: &str) -> &'static str {
+    let extension = match name.rsplit_once('.') {
+        Some((_, ext)) => ext.to_ascii_lowercase(),
+        None => return "",
+    };
+    match extension.as_str() {
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "avif" => "image/avif",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "",
+    }
+}
+
 /// File options
 #[derive(Debug, Clone, Default)]
 pub struct FileOptions {
@@ -263,4 +318,18 @@ mod tests {
         assert_eq!(file.name(), "test.txt");
         assert_eq!(file.size(), 7);
     }
+    
+    #[test]
+    fn test_file_from_path() {
+        let path = std::env::temp_dir().join(format!("fos-blob-test-{}.json", std::process::id()));
+        std::fs::write(&path, "{}").unwrap();
+        let file = File::from_path(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        
+        assert!(file.name().ends_with(".json"));
+        assert_eq!(file.mime_type(), "application/json");
+        assert_eq!(file.as_blob().text(), "{}");
+        assert!(file.last_modified() > 0);
+        assert_eq!(mime_type_for_name("README"), "");
+    }
 }
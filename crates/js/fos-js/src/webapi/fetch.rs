@@ -4,6 +4,8 @@
 
 use std::collections::HashMap;
 
+use super::abort::AbortSignal;
+
 /// HTTP method
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum HttpMethod {
@@ -470,6 +472,19 @@ impl FetchManager {
         id
     }
 
+    /// Start a fetch request that `signal` can abort; an aborted signal
+    /// rejects it with the signal's reason. Network loads take the
+    /// signal's `deadline()`, which bounds and cancels them in fos-net.
+    pub fn fetch_with_signal(&mut self, request: Request, signal: &AbortSignal) -> u64 {
+        if let Err(e) = signal.throw_if_aborted() {
+            let id = self.next_id;
+            self.next_id += 1;
+            self.pending.insert(id, FetchResult::Error(e.to_string()));
+            return id;
+        }
+        self.fetch(request)
+    }
+
     /// Get the result of a fetch request
     pub fn get_result(&mut self, id: u64) -> Option<FetchResult> {
         self.pending.remove(&id)
@@ -544,4 +559,16 @@ mod tests {
             _ => panic!("Expected success"),
         }
     }
+
+    #[test]
+    fn test_fetch_with_aborted_signal() {
+        let mut manager = FetchManager::new();
+        let signal = AbortSignal::timeout(0);
+        
+        let id = manager.fetch_with_signal(Request::new("https://example.com"), &signal);
+        match manager.get_result(id) {
+            Some(FetchResult::Error(reason)) => assert!(reason.contains("TimeoutError")),
+            _ => panic!("Expected abort"),
+        }
+    }
 }